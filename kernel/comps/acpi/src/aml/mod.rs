@@ -0,0 +1,461 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! A minimal interpreter of the ACPI Machine Language (AML).
+//!
+//! The interpreter loads the named objects defined in the AML code into a namespace. It can
+//! evaluate the data objects and the control methods that simply return a value, e.g., `_STA`
+//! methods that always return `0x0F`. The control methods that perform computations or access
+//! the hardware (e.g., via operation regions) are not supported.
+//!
+//! Reference: ACPI Specification, Version 6.5, Chapter 20 "ACPI Machine Language (AML)
+//! Specification".
+
+mod parser;
+
+use alloc::{
+    collections::btree_map::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use self::parser::{NameString, Parser, opcode};
+
+/// An error that occurs when loading or evaluating the AML code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AmlError {
+    /// The AML code is malformed or contains unknown opcodes.
+    Parse,
+    /// The object does not exist.
+    NotFound,
+    /// The object cannot be evaluated by the interpreter.
+    Unsupported,
+}
+
+/// A value of an evaluated object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AmlValue {
+    Integer(u64),
+    String(String),
+    Buffer(Vec<u8>),
+    Package(Vec<AmlValue>),
+}
+
+impl AmlValue {
+    pub fn as_integer(&self) -> Option<u64> {
+        match self {
+            Self::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_package(&self) -> Option<&[AmlValue]> {
+        match self {
+            Self::Package(elements) => Some(elements),
+            _ => None,
+        }
+    }
+}
+
+/// The kind of an object that contains other objects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeKind {
+    Scope,
+    Device,
+    Processor,
+    PowerResource,
+    ThermalZone,
+}
+
+#[derive(Debug)]
+enum Object {
+    Scope(ScopeKind),
+    /// A named data object, which is evaluated from its code.
+    Name(&'static [u8]),
+    Method {
+        arg_count: u8,
+        code: &'static [u8],
+    },
+    /// An alias of another object, whose name is relative to the alias.
+    Alias(NameString),
+    /// An object that cannot be evaluated, e.g., a mutex or an operation region.
+    Other,
+}
+
+/// The ACPI namespace.
+///
+/// The objects are identified by their absolute paths, e.g., `\_SB_.PCI0`, where the trailing
+/// underscores of the name segments are kept.
+#[derive(Debug)]
+pub struct Namespace {
+    objects: BTreeMap<String, Object>,
+}
+
+/// The revision of the interpreter, which is returned by the `Revision` opcode.
+const INTERPRETER_REVISION: u64 = 2;
+/// The maximum depth of the nested evaluations, which prevents the circular references from
+/// overflowing the stack.
+const MAX_EVAL_DEPTH: usize = 16;
+
+impl Namespace {
+    /// Loads the objects defined in the AML tables.
+    ///
+    /// If a term cannot be parsed, the remaining terms in the same scope are skipped, but the
+    /// terms in the enclosing scopes are still loaded.
+    pub fn load(tables: &[&'static [u8]]) -> Self {
+        let mut namespace = Self {
+            objects: BTreeMap::new(),
+        };
+        namespace
+            .objects
+            .insert(String::from(ROOT), Object::Scope(ScopeKind::Scope));
+
+        for table in tables.iter().copied() {
+            namespace.load_term_list(&mut Parser::new(table), ROOT);
+        }
+
+        namespace
+    }
+
+    fn load_term_list(&mut self, parser: &mut Parser<'static>, scope: &str) {
+        while !parser.is_empty() {
+            if let Err(err) = self.load_term(parser, scope) {
+                log::debug!("ACPI: failed to load the AML code in {}: {:?}", scope, err);
+                return;
+            }
+        }
+    }
+
+    fn load_term(&mut self, parser: &mut Parser<'static>, scope: &str) -> Result<(), AmlError> {
+        match parser.byte()? {
+            opcode::SCOPE => {
+                let mut body = parser.package()?;
+                let path =
+                    self.define(scope, &body.name_string()?, Object::Scope(ScopeKind::Scope))?;
+                self.load_term_list(&mut body, &path);
+            }
+            opcode::NAME => {
+                let name = parser.name_string()?;
+                let code = parser.rest();
+                parser.skip_data_ref_object()?;
+                let len = code.len() - parser.rest().len();
+                self.define(scope, &name, Object::Name(&code[..len]))?;
+            }
+            opcode::METHOD => {
+                let mut body = parser.package()?;
+                let name = body.name_string()?;
+                let flags = body.byte()?;
+                let method = Object::Method {
+                    arg_count: flags & 0b111,
+                    code: body.rest(),
+                };
+                self.define(scope, &name, method)?;
+            }
+            opcode::ALIAS => {
+                let source = parser.name_string()?;
+                let alias = parser.name_string()?;
+                self.define(scope, &alias, Object::Alias(source))?;
+            }
+            opcode::EXTERNAL => {
+                parser.name_string()?;
+                parser.bytes(2)?;
+            }
+            // The objects that are defined conditionally are not loaded.
+            opcode::IF | opcode::ELSE | opcode::WHILE => {
+                parser.package()?;
+            }
+            opcode::EXT_PREFIX => self.load_ext_term(parser, scope)?,
+            _ => return Err(AmlError::Parse),
+        }
+
+        Ok(())
+    }
+
+    fn load_ext_term(&mut self, parser: &mut Parser<'static>, scope: &str) -> Result<(), AmlError> {
+        let (kind, num_skipped) = match parser.byte()? {
+            opcode::ext::DEVICE => (ScopeKind::Device, 0),
+            opcode::ext::THERMAL_ZONE => (ScopeKind::ThermalZone, 0),
+            // The processor ID, the address and the length of the processor register block.
+            opcode::ext::PROCESSOR => (ScopeKind::Processor, 6),
+            // The system level and the resource order.
+            opcode::ext::POWER_RES => (ScopeKind::PowerResource, 3),
+            opcode::ext::OP_REGION => {
+                let name = parser.name_string()?;
+                // The region space, the offset, and the length.
+                parser.byte()?;
+                parser.skip_data_ref_object()?;
+                parser.skip_data_ref_object()?;
+                self.define(scope, &name, Object::Other)?;
+                return Ok(());
+            }
+            opcode::ext::MUTEX => {
+                let name = parser.name_string()?;
+                parser.byte()?;
+                self.define(scope, &name, Object::Other)?;
+                return Ok(());
+            }
+            opcode::ext::EVENT => {
+                let name = parser.name_string()?;
+                self.define(scope, &name, Object::Other)?;
+                return Ok(());
+            }
+            // The field units are not loaded since they cannot be evaluated.
+            opcode::ext::FIELD | opcode::ext::INDEX_FIELD | opcode::ext::BANK_FIELD => {
+                parser.package()?;
+                return Ok(());
+            }
+            _ => return Err(AmlError::Parse),
+        };
+
+        let mut body = parser.package()?;
+        let name = body.name_string()?;
+        body.bytes(num_skipped)?;
+        let path = self.define(scope, &name, Object::Scope(kind))?;
+        self.load_term_list(&mut body, &path);
+
+        Ok(())
+    }
+
+    /// Defines an object, returning its absolute path.
+    ///
+    /// A scope may be opened multiple times, in which case the first definition is kept.
+    fn define(
+        &mut self,
+        scope: &str,
+        name: &NameString,
+        object: Object,
+    ) -> Result<String, AmlError> {
+        let path = resolve(scope, name).ok_or(AmlError::Parse)?;
+        self.objects.entry(path.clone()).or_insert(object);
+        Ok(path)
+    }
+
+    /// Returns the absolute paths of the objects of the kind.
+    pub fn scopes_of_kind(&self, kind: ScopeKind) -> impl Iterator<Item = &str> {
+        self.objects
+            .iter()
+            .filter_map(move |(path, object)| match object {
+                Object::Scope(object_kind) if *object_kind == kind => Some(path.as_str()),
+                _ => None,
+            })
+    }
+
+    /// Returns whether the object exists.
+    pub fn contains(&self, path: &str) -> bool {
+        self.objects.contains_key(path)
+    }
+
+    /// Evaluates the object specified by its absolute path.
+    pub fn evaluate(&self, path: &str) -> Result<AmlValue, AmlError> {
+        self.evaluate_object(path, 0)
+    }
+
+    /// Evaluates a child object, e.g., the `_STA` object of a device.
+    pub fn evaluate_child(&self, path: &str, name: &str) -> Result<AmlValue, AmlError> {
+        self.evaluate(&child_path(path, name))
+    }
+
+    fn evaluate_object(&self, path: &str, depth: usize) -> Result<AmlValue, AmlError> {
+        if depth > MAX_EVAL_DEPTH {
+            return Err(AmlError::Unsupported);
+        }
+
+        match self.objects.get(path).ok_or(AmlError::NotFound)? {
+            Object::Name(code) => {
+                self.evaluate_data_object(&mut Parser::new(code), parent_path(path), depth)
+            }
+            Object::Method { arg_count: 0, code } => {
+                // Only the methods that return a value directly are supported.
+                let mut parser = Parser::new(code);
+                if parser.byte()? != opcode::RETURN {
+                    return Err(AmlError::Unsupported);
+                }
+                self.evaluate_data_object(&mut parser, path, depth)
+            }
+            Object::Alias(source) => {
+                let source_path = self.lookup(parent_path(path), source)?;
+                self.evaluate_object(&source_path, depth + 1)
+            }
+            Object::Scope(_) | Object::Method { .. } | Object::Other => Err(AmlError::Unsupported),
+        }
+    }
+
+    /// Evaluates a `DataRefObject`, where the names are evaluated in the scope.
+    fn evaluate_data_object(
+        &self,
+        parser: &mut Parser,
+        scope: &str,
+        depth: usize,
+    ) -> Result<AmlValue, AmlError> {
+        if parser.is_name_next() {
+            let name = parser.name_string()?;
+            let path = self.lookup(scope, &name)?;
+            return self.evaluate_object(&path, depth + 1);
+        }
+
+        let value = match parser.byte()? {
+            opcode::ZERO => AmlValue::Integer(0),
+            opcode::ONE => AmlValue::Integer(1),
+            opcode::ONES => AmlValue::Integer(u64::MAX),
+            opcode::BYTE_PREFIX => AmlValue::Integer(parser.integer(1)?),
+            opcode::WORD_PREFIX => AmlValue::Integer(parser.integer(2)?),
+            opcode::DWORD_PREFIX => AmlValue::Integer(parser.integer(4)?),
+            opcode::QWORD_PREFIX => AmlValue::Integer(parser.integer(8)?),
+            opcode::STRING_PREFIX => {
+                let mut string = String::new();
+                loop {
+                    match parser.byte()? {
+                        0 => break,
+                        byte => string.push(byte as char),
+                    }
+                }
+                AmlValue::String(string)
+            }
+            opcode::BUFFER => {
+                let mut body = parser.package()?;
+                let size = self.evaluate_integer(&mut body, scope, depth)?;
+                // The buffer is extended to the size, but not truncated if it is initialized
+                // with more bytes.
+                let mut bytes = body.rest().to_vec();
+                let size = usize::try_from(size).map_err(|_| AmlError::Parse)?;
+                bytes.resize(size.max(bytes.len()), 0);
+                AmlValue::Buffer(bytes)
+            }
+            opcode::PACKAGE => {
+                let mut body = parser.package()?;
+                let num_elements = body.byte()? as u64;
+                self.evaluate_package(body, num_elements, scope, depth)?
+            }
+            opcode::VAR_PACKAGE => {
+                let mut body = parser.package()?;
+                let num_elements = self.evaluate_integer(&mut body, scope, depth)?;
+                self.evaluate_package(body, num_elements, scope, depth)?
+            }
+            opcode::EXT_PREFIX if parser.byte()? == opcode::ext::REVISION => {
+                AmlValue::Integer(INTERPRETER_REVISION)
+            }
+            _ => return Err(AmlError::Unsupported),
+        };
+
+        Ok(value)
+    }
+
+    fn evaluate_integer(
+        &self,
+        parser: &mut Parser,
+        scope: &str,
+        depth: usize,
+    ) -> Result<u64, AmlError> {
+        self.evaluate_data_object(parser, scope, depth)?
+            .as_integer()
+            .ok_or(AmlError::Unsupported)
+    }
+
+    fn evaluate_package(
+        &self,
+        mut body: Parser,
+        num_elements: u64,
+        scope: &str,
+        depth: usize,
+    ) -> Result<AmlValue, AmlError> {
+        let mut elements = Vec::new();
+        while !body.is_empty() {
+            elements.push(self.evaluate_data_object(&mut body, scope, depth + 1)?);
+        }
+
+        // The elements that are not initialized are treated as zeros.
+        let num_elements = num_elements.try_into().map_err(|_| AmlError::Parse)?;
+        elements.resize(num_elements, AmlValue::Integer(0));
+
+        Ok(AmlValue::Package(elements))
+    }
+
+    /// Looks up an object by its name, which is relative to the scope.
+    ///
+    /// A single name segment is also searched in the parent scopes.
+    fn lookup(&self, scope: &str, name: &NameString) -> Result<String, AmlError> {
+        if !name.is_single_segment() {
+            return resolve(scope, name)
+                .filter(|path| self.contains(path))
+                .ok_or(AmlError::NotFound);
+        }
+
+        let mut scope = scope;
+        loop {
+            let path = child_path(scope, &name.segments[0]);
+            if self.contains(&path) {
+                return Ok(path);
+            }
+            if scope == ROOT {
+                return Err(AmlError::NotFound);
+            }
+            scope = parent_path(scope);
+        }
+    }
+}
+
+/// The path of the root scope.
+const ROOT: &str = "\\";
+
+fn child_path(path: &str, name: &str) -> String {
+    if path == ROOT {
+        format!("{}{}", ROOT, name)
+    } else {
+        format!("{}.{}", path, name)
+    }
+}
+
+/// Returns the path of the parent, which is the root itself if the path is the root.
+fn parent_path(path: &str) -> &str {
+    match path.rfind('.') {
+        Some(index) => &path[..index],
+        None => ROOT,
+    }
+}
+
+/// Resolves a name relative to the scope into an absolute path.
+fn resolve(scope: &str, name: &NameString) -> Option<String> {
+    let mut path = if name.is_absolute {
+        String::from(ROOT)
+    } else {
+        scope.to_string()
+    };
+    for _ in 0..name.num_parents {
+        if path == ROOT {
+            return None;
+        }
+        path = parent_path(&path).to_string();
+    }
+    for segment in name.segments.iter() {
+        path = child_path(&path, segment);
+    }
+
+    Some(path)
+}
+
+/// Returns the last name segment of the path.
+pub fn last_segment(path: &str) -> &str {
+    path.rsplit(['.', '\\']).next().unwrap()
+}
+
+/// Converts a compressed EISA ID (e.g., the value of `EisaId ("PNP0C0A")`) to a string.
+pub fn eisa_id_to_string(id: u64) -> String {
+    let id = (id as u32).swap_bytes();
+    let vendor = id >> 16;
+    let letter = |shift: u32| (((vendor >> shift) & 0x1F) as u8 + b'@') as char;
+
+    format!(
+        "{}{}{}{:04X}",
+        letter(10),
+        letter(5),
+        letter(0),
+        id & 0xFFFF
+    )
+}
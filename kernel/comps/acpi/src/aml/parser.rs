@@ -0,0 +1,249 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The parser of the AML byte stream.
+
+use alloc::{string::String, vec::Vec};
+
+use super::AmlError;
+
+/// The opcodes.
+pub(super) mod opcode {
+    pub(in crate::aml) const ZERO: u8 = 0x00;
+    pub(in crate::aml) const ONE: u8 = 0x01;
+    pub(in crate::aml) const ALIAS: u8 = 0x06;
+    pub(in crate::aml) const NAME: u8 = 0x08;
+    pub(in crate::aml) const BYTE_PREFIX: u8 = 0x0A;
+    pub(in crate::aml) const WORD_PREFIX: u8 = 0x0B;
+    pub(in crate::aml) const DWORD_PREFIX: u8 = 0x0C;
+    pub(in crate::aml) const STRING_PREFIX: u8 = 0x0D;
+    pub(in crate::aml) const QWORD_PREFIX: u8 = 0x0E;
+    pub(in crate::aml) const SCOPE: u8 = 0x10;
+    pub(in crate::aml) const BUFFER: u8 = 0x11;
+    pub(in crate::aml) const PACKAGE: u8 = 0x12;
+    pub(in crate::aml) const VAR_PACKAGE: u8 = 0x13;
+    pub(in crate::aml) const METHOD: u8 = 0x14;
+    pub(in crate::aml) const EXTERNAL: u8 = 0x15;
+    pub(in crate::aml) const EXT_PREFIX: u8 = 0x5B;
+    pub(in crate::aml) const IF: u8 = 0xA0;
+    pub(in crate::aml) const ELSE: u8 = 0xA1;
+    pub(in crate::aml) const WHILE: u8 = 0xA2;
+    pub(in crate::aml) const RETURN: u8 = 0xA4;
+    pub(in crate::aml) const ONES: u8 = 0xFF;
+
+    /// The opcodes following [`EXT_PREFIX`].
+    pub(in crate::aml) mod ext {
+        pub(in crate::aml) const MUTEX: u8 = 0x01;
+        pub(in crate::aml) const EVENT: u8 = 0x02;
+        pub(in crate::aml) const REVISION: u8 = 0x30;
+        pub(in crate::aml) const OP_REGION: u8 = 0x80;
+        pub(in crate::aml) const FIELD: u8 = 0x81;
+        pub(in crate::aml) const DEVICE: u8 = 0x82;
+        pub(in crate::aml) const PROCESSOR: u8 = 0x83;
+        pub(in crate::aml) const POWER_RES: u8 = 0x84;
+        pub(in crate::aml) const THERMAL_ZONE: u8 = 0x85;
+        pub(in crate::aml) const INDEX_FIELD: u8 = 0x86;
+        pub(in crate::aml) const BANK_FIELD: u8 = 0x87;
+    }
+}
+
+const ROOT_CHAR: u8 = b'\\';
+const PARENT_PREFIX_CHAR: u8 = b'^';
+const DUAL_NAME_PREFIX: u8 = 0x2E;
+const MULTI_NAME_PREFIX: u8 = 0x2F;
+const NULL_NAME: u8 = 0x00;
+
+/// A name that refers to an object in the namespace.
+#[derive(Debug)]
+pub(super) struct NameString {
+    /// Whether the name is relative to the root.
+    pub(super) is_absolute: bool,
+    /// The number of the parent prefixes, each of which refers to the parent scope.
+    pub(super) num_parents: usize,
+    /// The name segments, each of which has four characters.
+    pub(super) segments: Vec<String>,
+}
+
+impl NameString {
+    /// Returns whether the name is a single name segment, which is searched in the parent scopes
+    /// if it is not found in the current scope.
+    pub(super) fn is_single_segment(&self) -> bool {
+        !self.is_absolute && self.num_parents == 0 && self.segments.len() == 1
+    }
+}
+
+/// A cursor over the AML byte stream.
+pub(super) struct Parser<'a> {
+    code: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    pub(super) fn new(code: &'a [u8]) -> Self {
+        Self { code, pos: 0 }
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.pos >= self.code.len()
+    }
+
+    /// Returns the unparsed code.
+    pub(super) fn rest(&self) -> &'a [u8] {
+        &self.code[self.pos..]
+    }
+
+    pub(super) fn peek(&self) -> Result<u8, AmlError> {
+        self.code.get(self.pos).copied().ok_or(AmlError::Parse)
+    }
+
+    pub(super) fn byte(&mut self) -> Result<u8, AmlError> {
+        let byte = self.peek()?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    pub(super) fn bytes(&mut self, len: usize) -> Result<&'a [u8], AmlError> {
+        let end = self.pos.checked_add(len).ok_or(AmlError::Parse)?;
+        let bytes = self.code.get(self.pos..end).ok_or(AmlError::Parse)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    /// Parses an integer in little endian.
+    pub(super) fn integer(&mut self, len: usize) -> Result<u64, AmlError> {
+        let bytes = self.bytes(len)?;
+        Ok(bytes
+            .iter()
+            .rev()
+            .fold(0, |value, byte| (value << 8) | *byte as u64))
+    }
+
+    /// Parses a `PkgLength` and the following bytes, returning a parser of the bytes.
+    pub(super) fn package(&mut self) -> Result<Parser<'a>, AmlError> {
+        let start = self.pos;
+
+        // Bits 7:6 of the lead byte are the number of the following bytes. If there are none,
+        // bits 5:0 are the length. Otherwise, bits 3:0 are the least significant bits.
+        let lead = self.byte()?;
+        let num_following = (lead >> 6) as usize;
+        let len = if num_following == 0 {
+            (lead & 0x3F) as usize
+        } else {
+            let following = self.integer(num_following)? as usize;
+            (lead & 0x0F) as usize | (following << 4)
+        };
+
+        // The length includes the `PkgLength` itself.
+        let end = start.checked_add(len).ok_or(AmlError::Parse)?;
+        if end < self.pos || end > self.code.len() {
+            return Err(AmlError::Parse);
+        }
+        let body = &self.code[self.pos..end];
+        self.pos = end;
+
+        Ok(Parser::new(body))
+    }
+
+    /// Parses a `NameString`.
+    pub(super) fn name_string(&mut self) -> Result<NameString, AmlError> {
+        let mut name = NameString {
+            is_absolute: false,
+            num_parents: 0,
+            segments: Vec::new(),
+        };
+
+        if self.peek()? == ROOT_CHAR {
+            self.pos += 1;
+            name.is_absolute = true;
+        } else {
+            while self.peek()? == PARENT_PREFIX_CHAR {
+                self.pos += 1;
+                name.num_parents += 1;
+            }
+        }
+
+        let num_segments = match self.peek()? {
+            DUAL_NAME_PREFIX => {
+                self.pos += 1;
+                2
+            }
+            MULTI_NAME_PREFIX => {
+                self.pos += 1;
+                self.byte()? as usize
+            }
+            NULL_NAME => {
+                self.pos += 1;
+                0
+            }
+            _ => 1,
+        };
+        for _ in 0..num_segments {
+            name.segments.push(self.name_segment()?);
+        }
+
+        Ok(name)
+    }
+
+    fn name_segment(&mut self) -> Result<String, AmlError> {
+        let bytes = self.bytes(4)?;
+        if !is_lead_name_char(bytes[0])
+            || !bytes[1..]
+                .iter()
+                .all(|byte| is_lead_name_char(*byte) || byte.is_ascii_digit())
+        {
+            return Err(AmlError::Parse);
+        }
+
+        Ok(bytes.iter().map(|byte| *byte as char).collect())
+    }
+
+    /// Returns whether the next term is a `NameString`.
+    pub(super) fn is_name_next(&self) -> bool {
+        self.peek().is_ok_and(|byte| {
+            is_lead_name_char(byte)
+                || matches!(
+                    byte,
+                    ROOT_CHAR | PARENT_PREFIX_CHAR | DUAL_NAME_PREFIX | MULTI_NAME_PREFIX
+                )
+        })
+    }
+
+    /// Skips a `DataRefObject`, which is a data object or a name.
+    ///
+    /// The integer expressions (e.g., `Add`) are not supported.
+    pub(super) fn skip_data_ref_object(&mut self) -> Result<(), AmlError> {
+        if self.is_name_next() {
+            self.name_string()?;
+            return Ok(());
+        }
+
+        match self.byte()? {
+            opcode::ZERO | opcode::ONE | opcode::ONES => (),
+            opcode::BYTE_PREFIX => self.pos += 1,
+            opcode::WORD_PREFIX => self.pos += 2,
+            opcode::DWORD_PREFIX => self.pos += 4,
+            opcode::QWORD_PREFIX => self.pos += 8,
+            opcode::STRING_PREFIX => {
+                let len = self
+                    .rest()
+                    .iter()
+                    .position(|byte| *byte == 0)
+                    .ok_or(AmlError::Parse)?;
+                self.pos += len + 1;
+            }
+            opcode::BUFFER | opcode::PACKAGE | opcode::VAR_PACKAGE => {
+                self.package()?;
+            }
+            opcode::EXT_PREFIX if self.byte()? == opcode::ext::REVISION => (),
+            _ => return Err(AmlError::Parse),
+        }
+
+        if self.pos > self.code.len() {
+            return Err(AmlError::Parse);
+        }
+        Ok(())
+    }
+}
+
+fn is_lead_name_char(byte: u8) -> bool {
+    byte.is_ascii_uppercase() || byte == b'_'
+}
@@ -0,0 +1,187 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The ACPI control method batteries.
+//!
+//! Reference: ACPI Specification, Version 6.5, Section 10.2 "Control Method Batteries".
+
+use alloc::string::String;
+
+use crate::{
+    aml::{self, AmlValue},
+    namespace,
+};
+
+/// The `_HID` of the control method batteries.
+pub(crate) const BATTERY_HID: &str = "PNP0C0A";
+
+/// The value that indicates an unknown quantity.
+const UNKNOWN: u64 = 0xFFFF_FFFF;
+
+/// A control method battery.
+#[derive(Debug)]
+pub struct Battery {
+    path: String,
+}
+
+/// The static information of a battery.
+#[derive(Debug)]
+pub struct BatteryInfo {
+    pub power_unit: PowerUnit,
+    pub design_capacity: Option<u32>,
+    pub last_full_capacity: Option<u32>,
+    /// The design voltage in millivolts.
+    pub design_voltage: Option<u32>,
+    pub cycle_count: Option<u32>,
+    pub model: String,
+    pub serial_number: String,
+    pub technology: String,
+    pub manufacturer: String,
+}
+
+/// The unit of the capacities and the rates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerUnit {
+    /// The capacities are in mWh and the rates are in mW.
+    MilliWatt,
+    /// The capacities are in mAh and the rates are in mA.
+    MilliAmpere,
+}
+
+/// The current status of a battery.
+#[derive(Debug)]
+pub struct BatteryStatus {
+    pub state: ChargingState,
+    /// Whether the battery is in the critical energy state.
+    pub is_critical: bool,
+    pub rate: Option<u32>,
+    pub remaining_capacity: Option<u32>,
+    /// The present voltage in millivolts.
+    pub voltage: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChargingState {
+    Charging,
+    Discharging,
+    /// The battery is neither charging nor discharging, e.g., because it is full.
+    Idle,
+}
+
+impl Battery {
+    pub(crate) fn new(path: String) -> Self {
+        Self { path }
+    }
+
+    /// Returns the name of the battery in the ACPI namespace, e.g., `BAT0`.
+    pub fn name(&self) -> &str {
+        aml::last_segment(&self.path)
+    }
+
+    /// Returns whether the battery is inserted.
+    pub fn is_present(&self) -> bool {
+        const STA_BATTERY_PRESENT: u64 = 1 << 4;
+
+        match self.evaluate("_STA").and_then(|value| value.as_integer()) {
+            Some(status) => status & STA_BATTERY_PRESENT != 0,
+            None => true,
+        }
+    }
+
+    /// Returns the static information of the battery.
+    ///
+    /// The information is evaluated from `_BIX` if it exists, or from `_BIF` otherwise.
+    pub fn info(&self) -> Option<BatteryInfo> {
+        if let Some(bix) = self.evaluate("_BIX") {
+            // The layout is `[revision, unit, design capacity, last full capacity, technology,
+            // design voltage, warning, low, cycle count, ..., model, serial, type, OEM]`.
+            let fields = bix.as_package()?;
+            return Some(BatteryInfo {
+                power_unit: power_unit_of(fields.get(1)?)?,
+                design_capacity: quantity_of(fields.get(2)),
+                last_full_capacity: quantity_of(fields.get(3)),
+                design_voltage: quantity_of(fields.get(5)),
+                cycle_count: quantity_of(fields.get(8)),
+                model: text_of(fields.get(16)),
+                serial_number: text_of(fields.get(17)),
+                technology: text_of(fields.get(18)),
+                manufacturer: text_of(fields.get(19)),
+            });
+        }
+
+        // The layout is `[unit, design capacity, last full capacity, technology, design voltage,
+        // warning, low, granularity 1, granularity 2, model, serial, type, OEM]`.
+        let bif = self.evaluate("_BIF")?;
+        let fields = bif.as_package()?;
+        Some(BatteryInfo {
+            power_unit: power_unit_of(fields.first()?)?,
+            design_capacity: quantity_of(fields.get(1)),
+            last_full_capacity: quantity_of(fields.get(2)),
+            design_voltage: quantity_of(fields.get(4)),
+            cycle_count: None,
+            model: text_of(fields.get(9)),
+            serial_number: text_of(fields.get(10)),
+            technology: text_of(fields.get(11)),
+            manufacturer: text_of(fields.get(12)),
+        })
+    }
+
+    /// Returns the current status of the battery.
+    pub fn status(&self) -> Option<BatteryStatus> {
+        const STATE_DISCHARGING: u64 = 1 << 0;
+        const STATE_CHARGING: u64 = 1 << 1;
+        const STATE_CRITICAL: u64 = 1 << 2;
+
+        // The layout is `[state, rate, remaining capacity, voltage]`.
+        let bst = self.evaluate("_BST")?;
+        let fields = bst.as_package()?;
+
+        let state_bits = fields.first()?.as_integer()?;
+        let state = if state_bits & STATE_CHARGING != 0 {
+            ChargingState::Charging
+        } else if state_bits & STATE_DISCHARGING != 0 {
+            ChargingState::Discharging
+        } else {
+            ChargingState::Idle
+        };
+
+        Some(BatteryStatus {
+            state,
+            is_critical: state_bits & STATE_CRITICAL != 0,
+            rate: quantity_of(fields.get(1)),
+            remaining_capacity: quantity_of(fields.get(2)),
+            voltage: quantity_of(fields.get(3)),
+        })
+    }
+
+    fn evaluate(&self, name: &str) -> Option<AmlValue> {
+        namespace()?.evaluate_child(&self.path, name).ok()
+    }
+}
+
+fn power_unit_of(value: &AmlValue) -> Option<PowerUnit> {
+    match value.as_integer()? {
+        0 => Some(PowerUnit::MilliWatt),
+        1 => Some(PowerUnit::MilliAmpere),
+        _ => None,
+    }
+}
+
+fn quantity_of(value: Option<&AmlValue>) -> Option<u32> {
+    match value?.as_integer()? {
+        UNKNOWN => None,
+        quantity => u32::try_from(quantity).ok(),
+    }
+}
+
+/// Converts a string or a buffer to a string, which is empty if the value is not a string.
+fn text_of(value: Option<&AmlValue>) -> String {
+    match value {
+        Some(AmlValue::String(string)) => string.clone(),
+        Some(AmlValue::Buffer(bytes)) => bytes
+            .iter()
+            .take_while(|byte| **byte != 0)
+            .map(|byte| *byte as char)
+            .collect(),
+        _ => String::new(),
+    }
+}
@@ -0,0 +1,87 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The ACPI fixed-hardware power button.
+
+use alloc::{
+    string::{String, ToString},
+    sync::Arc,
+};
+
+use aster_input::{
+    event_type_codes::{EventTypes, KeyCode, KeyStatus, SynEvent},
+    input_dev::{InputCapability, InputDevice, InputEvent, InputId, RegisteredInputDevice},
+};
+use spin::Once;
+
+static REGISTERED_DEVICE: Once<RegisteredInputDevice> = Once::new();
+
+pub(crate) fn init() {
+    let registered_device = aster_input::register_device(Arc::new(PowerButton::new()));
+    REGISTERED_DEVICE.call_once(|| registered_device);
+}
+
+/// Reports that the power button is pressed.
+///
+/// The hardware only reports the presses, so a release is reported immediately after each press.
+pub(crate) fn report_power_button() {
+    let Some(registered_device) = REGISTERED_DEVICE.get() else {
+        return;
+    };
+
+    let events = [
+        InputEvent::from_key_and_status(KeyCode::Power, KeyStatus::Pressed),
+        InputEvent::from_sync_event(SynEvent::Report),
+        InputEvent::from_key_and_status(KeyCode::Power, KeyStatus::Released),
+        InputEvent::from_sync_event(SynEvent::Report),
+    ];
+    registered_device.submit_events(&events);
+}
+
+#[derive(Debug)]
+struct PowerButton {
+    name: String,
+    phys: String,
+    uniq: String,
+    id: InputId,
+    capability: InputCapability,
+}
+
+impl PowerButton {
+    fn new() -> Self {
+        let mut capability = InputCapability::new();
+        capability.set_supported_event_type(EventTypes::KEY);
+        capability.set_supported_event_type(EventTypes::SYN);
+        capability.set_supported_key(KeyCode::Power);
+
+        // Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/drivers/acpi/button.c#L559>
+        Self {
+            name: "Power Button".to_string(),
+            phys: "LNXPWRBN/button/input0".to_string(),
+            uniq: "".to_string(),
+            id: InputId::new(InputId::BUS_HOST, 0x0000, 0x0001, 0x0000),
+            capability,
+        }
+    }
+}
+
+impl InputDevice for PowerButton {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn phys(&self) -> &str {
+        &self.phys
+    }
+
+    fn uniq(&self) -> &str {
+        &self.uniq
+    }
+
+    fn id(&self) -> InputId {
+        self.id
+    }
+
+    fn capability(&self) -> &InputCapability {
+        &self.capability
+    }
+}
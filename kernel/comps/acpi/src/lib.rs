@@ -0,0 +1,193 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The ACPI subsystem of Asterinas.
+//!
+//! The subsystem takes the control of the ACPI fixed hardware from the firmware. It reports the
+//! presses of the power button to the input subsystem, powers off the machine by entering the
+//! soft-off (S5) sleeping state, and suspends the machine to RAM by entering the S3 sleeping
+//! state.
+//!
+//! The subsystem also loads the ACPI namespace from the AML code in the DSDT and the SSDTs, from
+//! which the [`ThermalZone`]s and the [`Battery`]s are discovered.
+
+#![no_std]
+#![deny(unsafe_code)]
+#![cfg(target_arch = "x86_64")]
+
+extern crate alloc;
+
+pub mod aml;
+mod battery;
+mod button;
+mod pm;
+mod thermal;
+
+use alloc::{string::String, sync::Arc, vec::Vec};
+
+use component::{ComponentInitError, init_component};
+use ostd::{
+    arch::kernel::{ACPI_INFO, AML_TABLES},
+    power::{
+        DevicePmOps, ExitCode, inject_poweroff_handler, inject_suspend_handler,
+        register_device_pm_ops,
+    },
+};
+use spin::Once;
+
+use self::{
+    aml::{AmlValue, Namespace, ScopeKind},
+    pm::SleepType,
+};
+pub use self::{
+    battery::{Battery, BatteryInfo, BatteryStatus, ChargingState, PowerUnit},
+    thermal::ThermalZone,
+};
+
+static NAMESPACE: Once<Namespace> = Once::new();
+
+static THERMAL_ZONES: Once<Vec<ThermalZone>> = Once::new();
+
+static BATTERIES: Once<Vec<Battery>> = Once::new();
+
+/// The sleep type values of the soft-off (S5) state.
+static S5_SLEEP_TYPE: Once<SleepType> = Once::new();
+
+/// The sleep type values of the suspend-to-RAM (S3) state.
+static S3_SLEEP_TYPE: Once<SleepType> = Once::new();
+
+#[init_component]
+fn acpi_init() -> Result<(), ComponentInitError> {
+    let tables: Vec<&'static [u8]> = AML_TABLES
+        .get()
+        .unwrap()
+        .iter()
+        .map(|table| &**table)
+        .collect();
+    let namespace = NAMESPACE.call_once(|| Namespace::load(&tables));
+
+    THERMAL_ZONES.call_once(|| {
+        namespace
+            .scopes_of_kind(ScopeKind::ThermalZone)
+            .map(|path| ThermalZone::new(String::from(path)))
+            .collect()
+    });
+    BATTERIES.call_once(|| {
+        devices_with_hid(namespace, battery::BATTERY_HID)
+            .map(|path| Battery::new(String::from(path)))
+            .collect()
+    });
+
+    let Some(pm_registers) = ACPI_INFO.get().unwrap().pm_registers.as_ref() else {
+        return Ok(());
+    };
+    if let Err(err) = pm::init(pm_registers) {
+        log::warn!("ACPI: failed to initialize the PM registers: {:?}", err);
+        return Ok(());
+    }
+    button::init();
+
+    match sleep_type_of(namespace, "\\_S5_") {
+        Some(sleep_type) => {
+            S5_SLEEP_TYPE.call_once(|| sleep_type);
+            // This takes no effect if a poweroff handler has been injected, e.g., on QEMU.
+            inject_poweroff_handler(poweroff);
+        }
+        None => log::warn!("ACPI: the soft-off (S5) state is not supported"),
+    }
+
+    register_device_pm_ops(Arc::new(PmRegistersOps));
+    match sleep_type_of(namespace, "\\_S3_") {
+        Some(sleep_type) => {
+            S3_SLEEP_TYPE.call_once(|| sleep_type);
+            inject_suspend_handler(suspend_to_ram);
+        }
+        None => log::info!("ACPI: the suspend-to-RAM (S3) state is not supported"),
+    }
+
+    Ok(())
+}
+
+/// Returns the ACPI namespace.
+pub fn namespace() -> Option<&'static Namespace> {
+    NAMESPACE.get()
+}
+
+/// Returns the thermal zones.
+pub fn thermal_zones() -> &'static [ThermalZone] {
+    THERMAL_ZONES.get().map_or(&[], Vec::as_slice)
+}
+
+/// Returns the batteries, including the battery slots where no battery is inserted.
+pub fn batteries() -> &'static [Battery] {
+    BATTERIES.get().map_or(&[], Vec::as_slice)
+}
+
+/// Returns the present devices with the hardware ID.
+fn devices_with_hid<'a>(namespace: &'a Namespace, hid: &'a str) -> impl Iterator<Item = &'a str> {
+    const STA_PRESENT: u64 = 1 << 0;
+
+    namespace
+        .scopes_of_kind(ScopeKind::Device)
+        .filter(move |path| match namespace.evaluate_child(path, "_HID") {
+            Ok(AmlValue::Integer(id)) => aml::eisa_id_to_string(id) == hid,
+            Ok(AmlValue::String(id)) => id == hid,
+            _ => false,
+        })
+        .filter(move |path| {
+            // A device without `_STA` is always present.
+            namespace
+                .evaluate_child(path, "_STA")
+                .ok()
+                .and_then(|status| status.as_integer())
+                .is_none_or(|status| status & STA_PRESENT != 0)
+        })
+}
+
+/// Evaluates the sleep type values of a sleeping state, e.g., `\_S5_`.
+fn sleep_type_of(namespace: &Namespace, path: &str) -> Option<SleepType> {
+    let value = namespace.evaluate(path).ok()?;
+    let fields = value.as_package()?;
+
+    Some(SleepType {
+        pm1a: fields.first()?.as_integer()? as u8,
+        pm1b: fields.get(1)?.as_integer()? as u8,
+    })
+}
+
+fn poweroff(_code: ExitCode) {
+    // TODO: Evaluate the `_PTS` method, which requires an argument and is not supported by the
+    // AML interpreter.
+
+    // If possible, keep this method panic-free because it may be called by the panic handler.
+    let Some(sleep_type) = S5_SLEEP_TYPE.get() else {
+        return;
+    };
+
+    let _guard = ostd::irq::disable_local();
+    pm::enter_sleep_state(*sleep_type);
+}
+
+fn suspend_to_ram() {
+    // TODO: Evaluate the `_PTS` and `_WAK` methods. See `poweroff` for details.
+
+    if let Some(sleep_type) = S3_SLEEP_TYPE.get() {
+        pm::enter_sleep_state(*sleep_type);
+    }
+}
+
+/// The power management operations of the ACPI fixed hardware.
+struct PmRegistersOps;
+
+impl DevicePmOps for PmRegistersOps {
+    fn name(&self) -> &str {
+        "acpi-pm"
+    }
+
+    fn suspend(&self) -> ostd::Result<()> {
+        Ok(())
+    }
+
+    fn resume(&self) {
+        pm::resume();
+    }
+}
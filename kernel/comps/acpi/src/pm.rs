@@ -0,0 +1,266 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The power management (PM) registers of the ACPI fixed hardware.
+//!
+//! Reference: ACPI Specification, Version 6.5, Section 4.8.3 "PM1 Event Grouping" and Section
+//! 4.8.3.2 "PM1 Control Registers".
+
+use core::{hint::spin_loop, time::Duration};
+
+use ostd::{
+    arch::{
+        device::io_port::{ReadWriteAccess, WriteOnlyAccess},
+        irq::{IRQ_CHIP, MappedIrqLine},
+        kernel::AcpiPmRegisters,
+        trap::TrapFrame,
+    },
+    io::IoPort,
+    irq::IrqLine,
+    timer::Jiffies,
+};
+use spin::Once;
+
+use crate::button;
+
+/// The bits in the PM1 status registers, which are cleared by writing ones.
+mod pm1_status {
+    pub(super) const PWRBTN_STS: u16 = 1 << 8;
+    pub(super) const WAK_STS: u16 = 1 << 15;
+}
+
+/// The bits in the PM1 enable registers.
+mod pm1_enable {
+    pub(super) const PWRBTN_EN: u16 = 1 << 8;
+}
+
+/// The bits in the PM1 control registers.
+mod pm1_control {
+    pub(super) const SCI_EN: u16 = 1 << 0;
+    pub(super) const SLP_TYP_SHIFT: u16 = 10;
+    pub(super) const SLP_TYP_MASK: u16 = 0b111 << SLP_TYP_SHIFT;
+    pub(super) const SLP_EN: u16 = 1 << 13;
+}
+
+/// An error that occurs when taking the control of the PM registers.
+#[derive(Debug)]
+pub(crate) enum PmInitError {
+    /// The I/O ports of the registers are occupied.
+    IoPortUnavailable,
+    /// The firmware does not transfer the ownership of the registers.
+    EnableFailed,
+    /// The SCI cannot be mapped to an IRQ line.
+    IrqUnavailable,
+}
+
+/// The sleep type values of a sleeping state, which are written to the `SLP_TYP` fields of the
+/// PM1a and PM1b control registers.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SleepType {
+    pub(crate) pm1a: u8,
+    pub(crate) pm1b: u8,
+}
+
+/// A group of the PM1 registers, i.e., the PM1a or PM1b registers.
+struct Pm1Group {
+    status: IoPort<u16, ReadWriteAccess>,
+    enable: IoPort<u16, ReadWriteAccess>,
+    control: IoPort<u16, ReadWriteAccess>,
+}
+
+impl Pm1Group {
+    fn acquire(event: u16, event_len: u8, control: u16) -> Result<Self, PmInitError> {
+        let acquire_port = |port| IoPort::acquire(port).map_err(|_| PmInitError::IoPortUnavailable);
+
+        Ok(Self {
+            status: acquire_port(event)?,
+            enable: acquire_port(event + (event_len / 2) as u16)?,
+            control: acquire_port(control)?,
+        })
+    }
+}
+
+struct Pm1Registers {
+    a: Pm1Group,
+    b: Option<Pm1Group>,
+    smi_command_and_acpi_enable: Option<(u16, u8)>,
+}
+
+impl Pm1Registers {
+    fn groups(&self) -> impl Iterator<Item = &Pm1Group> {
+        core::iter::once(&self.a).chain(self.b.as_ref())
+    }
+}
+
+static PM1_REGISTERS: Once<Pm1Registers> = Once::new();
+
+/// The IRQ line of the system control interrupt (SCI).
+static SCI_IRQ_LINE: Once<MappedIrqLine> = Once::new();
+
+pub(crate) fn init(registers: &AcpiPmRegisters) -> Result<(), PmInitError> {
+    let pm1_a = Pm1Group::acquire(
+        registers.pm1a_event,
+        registers.pm1_event_len,
+        registers.pm1a_control,
+    )?;
+    let pm1_b = match (registers.pm1b_event, registers.pm1b_control) {
+        (Some(event), Some(control)) => {
+            Some(Pm1Group::acquire(event, registers.pm1_event_len, control)?)
+        }
+        _ => None,
+    };
+
+    if pm1_a.control.read() & pm1_control::SCI_EN == 0 {
+        enable_acpi_mode(&pm1_a, registers.smi_command_and_acpi_enable)?;
+    }
+
+    let pm1 = PM1_REGISTERS.call_once(|| Pm1Registers {
+        a: pm1_a,
+        b: pm1_b,
+        smi_command_and_acpi_enable: registers.smi_command_and_acpi_enable,
+    });
+    enable_events(pm1);
+
+    // TODO: The SCI is a shareable, level-triggered, active-low interrupt unless the MADT
+    // overrides it, but the I/O APIC pins are always configured as edge-triggered and
+    // active-high. This works on QEMU, which reports an interrupt source override for the SCI.
+    let mut irq_line = IrqLine::alloc()
+        .and_then(|irq_line| {
+            IRQ_CHIP
+                .get()
+                .unwrap()
+                .map_isa_pin_to(irq_line, registers.sci_interrupt)
+        })
+        .map_err(|_| PmInitError::IrqUnavailable)?;
+    irq_line.on_active(handle_sci);
+    SCI_IRQ_LINE.call_once(|| irq_line);
+
+    Ok(())
+}
+
+/// Clears the stale events and enables the power button events.
+fn enable_events(pm1: &Pm1Registers) {
+    for group in pm1.groups() {
+        group
+            .status
+            .write(pm1_status::PWRBTN_STS | pm1_status::WAK_STS);
+        group.enable.write(pm1_enable::PWRBTN_EN);
+    }
+}
+
+/// Restores the PM registers after the system wakes up from a sleeping state.
+pub(crate) fn resume() {
+    let Some(pm1) = PM1_REGISTERS.get() else {
+        return;
+    };
+
+    // The firmware should restore the ACPI mode before jumping to the waking vector, but it does
+    // not hurt to check it.
+    if pm1.a.control.read() & pm1_control::SCI_EN == 0
+        && let Err(err) = enable_acpi_mode(&pm1.a, pm1.smi_command_and_acpi_enable)
+    {
+        log::warn!(
+            "ACPI: failed to enable the ACPI mode after waking up: {:?}",
+            err
+        );
+    }
+
+    enable_events(pm1);
+}
+
+/// Transfers the ownership of the PM registers from the firmware to the OS.
+fn enable_acpi_mode(
+    pm1_a: &Pm1Group,
+    smi_command_and_acpi_enable: Option<(u16, u8)>,
+) -> Result<(), PmInitError> {
+    // The firmware should respond within three seconds.
+    //
+    // Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/drivers/acpi/acpica/hwacpi.c#L93>
+    const ENABLE_TIMEOUT: Duration = Duration::from_secs(3);
+
+    let Some((smi_command, acpi_enable)) = smi_command_and_acpi_enable else {
+        return Err(PmInitError::EnableFailed);
+    };
+    let smi_command_port: IoPort<u8, WriteOnlyAccess> =
+        IoPort::acquire(smi_command).map_err(|_| PmInitError::IoPortUnavailable)?;
+    smi_command_port.write(acpi_enable);
+
+    let deadline = Jiffies::elapsed().as_duration() + ENABLE_TIMEOUT;
+    while pm1_a.control.read() & pm1_control::SCI_EN == 0 {
+        if Jiffies::elapsed().as_duration() > deadline {
+            return Err(PmInitError::EnableFailed);
+        }
+        spin_loop();
+    }
+
+    Ok(())
+}
+
+fn handle_sci(_trap_frame: &TrapFrame) {
+    let Some(pm1) = PM1_REGISTERS.get() else {
+        return;
+    };
+
+    let mut is_power_button_pressed = false;
+    for group in pm1.groups() {
+        let status = group.status.read();
+        if status & pm1_status::PWRBTN_STS != 0 {
+            group.status.write(pm1_status::PWRBTN_STS);
+            is_power_button_pressed = true;
+        }
+    }
+
+    if is_power_button_pressed {
+        button::report_power_button();
+    }
+}
+
+/// Enters the sleeping state.
+///
+/// This function returns if the system fails to enter the sleeping state, or if the system wakes
+/// up without losing the CPU context. The latter never happens for the soft-off (S5) state or the
+/// suspend-to-RAM (S3) state.
+///
+/// This function should be called with the local IRQs disabled.
+pub(crate) fn enter_sleep_state(sleep_type: SleepType) {
+    let Some(pm1) = PM1_REGISTERS.get() else {
+        return;
+    };
+
+    for group in pm1.groups() {
+        group.status.write(pm1_status::WAK_STS);
+    }
+
+    // The sleep types must be written before setting `SLP_EN`.
+    //
+    // Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/drivers/acpi/acpica/hwsleep.c#L104>
+    let slp_typ_of =
+        |value: u8| ((value as u16) << pm1_control::SLP_TYP_SHIFT) & pm1_control::SLP_TYP_MASK;
+    let control_a =
+        (pm1.a.control.read() & !pm1_control::SLP_TYP_MASK) | slp_typ_of(sleep_type.pm1a);
+    let control_b = pm1.b.as_ref().map(|group| {
+        (group.control.read() & !pm1_control::SLP_TYP_MASK) | slp_typ_of(sleep_type.pm1b)
+    });
+
+    pm1.a.control.write(control_a);
+    if let (Some(group), Some(control)) = (&pm1.b, control_b) {
+        group.control.write(control);
+    }
+
+    pm1.a.control.write(control_a | pm1_control::SLP_EN);
+    if let (Some(group), Some(control)) = (&pm1.b, control_b) {
+        group.control.write(control | pm1_control::SLP_EN);
+    }
+
+    // Wait for a while since the hardware may take some time to enter the sleeping state. Each
+    // access to the I/O ports takes about one microsecond, so this waits for about one second.
+    const MAX_WAKE_POLLS: usize = 1_000_000;
+    for _ in 0..MAX_WAKE_POLLS {
+        if pm1
+            .groups()
+            .any(|group| group.status.read() & pm1_status::WAK_STS != 0)
+        {
+            return;
+        }
+        spin_loop();
+    }
+}
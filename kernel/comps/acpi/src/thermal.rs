@@ -0,0 +1,55 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The ACPI thermal zones.
+//!
+//! Reference: ACPI Specification, Version 6.5, Chapter 11 "Thermal Management".
+
+use alloc::string::String;
+
+use crate::{aml, namespace};
+
+/// A thermal zone, which is a region whose temperature is reported by the firmware.
+#[derive(Debug)]
+pub struct ThermalZone {
+    path: String,
+}
+
+impl ThermalZone {
+    pub(crate) fn new(path: String) -> Self {
+        Self { path }
+    }
+
+    /// Returns the name of the thermal zone in the ACPI namespace, e.g., `TZ00`.
+    pub fn name(&self) -> &str {
+        aml::last_segment(&self.path)
+    }
+
+    /// Returns the current temperature in millidegrees Celsius.
+    ///
+    /// This returns `None` if the temperature cannot be evaluated, e.g., because the firmware
+    /// reads it from the embedded controller, which is not supported.
+    pub fn temperature(&self) -> Option<i32> {
+        self.evaluate_temperature("_TMP")
+    }
+
+    /// Returns the temperature at which the system should be shut down, in millidegrees Celsius.
+    pub fn critical_temperature(&self) -> Option<i32> {
+        self.evaluate_temperature("_CRT")
+    }
+
+    /// Returns the temperature at which the system should be throttled, in millidegrees Celsius.
+    pub fn passive_temperature(&self) -> Option<i32> {
+        self.evaluate_temperature("_PSV")
+    }
+
+    fn evaluate_temperature(&self, name: &str) -> Option<i32> {
+        let value = namespace()?
+            .evaluate_child(&self.path, name)
+            .ok()?
+            .as_integer()?;
+
+        // The temperatures are in tenths of degrees Kelvin.
+        let decikelvins = i64::try_from(value).ok()?;
+        i32::try_from(decikelvins * 100 - 273_150).ok()
+    }
+}
@@ -0,0 +1,249 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The command structures of AHCI and the ATA commands.
+//!
+//! Reference: Serial ATA AHCI Specification, Revision 1.3.1, Section 4.2, and ATA/ATAPI Command
+//! Set - 3 (ACS-3).
+
+use alloc::{string::String, vec::Vec};
+
+use ostd::mm::PAGE_SIZE;
+
+/// The opcodes of the ATA commands.
+pub(crate) mod ata_opcode {
+    pub(crate) const READ_DMA_EXT: u8 = 0x25;
+    pub(crate) const WRITE_DMA_EXT: u8 = 0x35;
+    pub(crate) const READ_FPDMA_QUEUED: u8 = 0x60;
+    pub(crate) const WRITE_FPDMA_QUEUED: u8 = 0x61;
+    pub(crate) const IDENTIFY_DEVICE: u8 = 0xEC;
+    pub(crate) const FLUSH_CACHE_EXT: u8 = 0xEA;
+}
+
+/// The offset of the physical region descriptor table in a command table.
+pub(crate) const PRDT_OFFSET: usize = 0x80;
+/// The size of a command table, which is one page for each command slot.
+pub(crate) const CMD_TABLE_SIZE: usize = PAGE_SIZE;
+/// The maximum number of physical region descriptors in a command table.
+pub(crate) const MAX_PRDS: usize = (CMD_TABLE_SIZE - PRDT_OFFSET) / size_of::<Prd>();
+/// The maximum number of bytes that a physical region descriptor can describe.
+pub(crate) const MAX_PRD_SIZE: usize = 4 << 20;
+
+/// A command header in the command list.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(crate) struct CommandHeader {
+    /// The command FIS length in dwords in bits 4:0 and the Write bit in bit 6.
+    flags: u16,
+    /// The number of the physical region descriptors.
+    prdtl: u16,
+    /// The number of bytes that have been transferred.
+    prdbc: u32,
+    /// The device address of the command table.
+    ctba: u64,
+    _reserved: [u32; 4],
+}
+
+impl CommandHeader {
+    /// The Write bit, which indicates the direction is from the memory to the device.
+    const WRITE: u16 = 1 << 6;
+
+    pub(crate) fn new(ctba: u64, nr_prds: usize, is_write: bool) -> Self {
+        let mut flags = (size_of::<RegisterFis>() / size_of::<u32>()) as u16;
+        if is_write {
+            flags |= Self::WRITE;
+        }
+
+        Self {
+            flags,
+            prdtl: nr_prds as u16,
+            ctba,
+            ..Default::default()
+        }
+    }
+}
+
+/// A Register - Host to Device FIS, which is the command FIS at the start of a command table.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(crate) struct RegisterFis {
+    fis_type: u8,
+    /// The Command bit in bit 7, which is set for commands.
+    flags: u8,
+    command: u8,
+    feature_low: u8,
+    lba_low: [u8; 3],
+    device: u8,
+    lba_high: [u8; 3],
+    feature_high: u8,
+    count: u16,
+    icc: u8,
+    control: u8,
+    _reserved: [u8; 4],
+}
+
+impl RegisterFis {
+    const TYPE: u8 = 0x27;
+    const COMMAND: u8 = 1 << 7;
+    /// The bit of the Device field that selects the LBA addressing.
+    const DEVICE_LBA: u8 = 1 << 6;
+    /// The bit of the Device field that forces the unit access (FUA) of a queued write.
+    const DEVICE_FUA: u8 = 1 << 7;
+
+    fn new(command: u8) -> Self {
+        Self {
+            fis_type: Self::TYPE,
+            flags: Self::COMMAND,
+            command,
+            ..Default::default()
+        }
+    }
+
+    /// Creates an IDENTIFY DEVICE command, which reads 512 bytes of the identify data.
+    pub(crate) fn identify() -> Self {
+        Self::new(ata_opcode::IDENTIFY_DEVICE)
+    }
+
+    /// Creates a READ DMA EXT or WRITE DMA EXT command.
+    ///
+    /// `nr_blocks` must be in `1..=65536`.
+    pub(crate) fn read_write_dma(is_write: bool, lba: u64, nr_blocks: u32) -> Self {
+        let opcode = if is_write {
+            ata_opcode::WRITE_DMA_EXT
+        } else {
+            ata_opcode::READ_DMA_EXT
+        };
+
+        let mut fis = Self::new(opcode);
+        fis.set_lba(lba);
+        // A count of zero means 65536 blocks.
+        fis.count = (nr_blocks as u16).to_le();
+        fis
+    }
+
+    /// Creates a READ FPDMA QUEUED or WRITE FPDMA QUEUED command of the native command queuing
+    /// (NCQ).
+    ///
+    /// `nr_blocks` must be in `1..=65536`. The tag must be the index of the command slot. If
+    /// `is_fua` is true, the command completes only after the data are persistent.
+    pub(crate) fn read_write_fpdma(
+        is_write: bool,
+        is_fua: bool,
+        lba: u64,
+        nr_blocks: u32,
+        tag: u8,
+    ) -> Self {
+        let opcode = if is_write {
+            ata_opcode::WRITE_FPDMA_QUEUED
+        } else {
+            ata_opcode::READ_FPDMA_QUEUED
+        };
+
+        let mut fis = Self::new(opcode);
+        fis.set_lba(lba);
+        // The block count is in the Feature field, and the tag is in the Count field.
+        let [feature_low, feature_high] = (nr_blocks as u16).to_le_bytes();
+        fis.feature_low = feature_low;
+        fis.feature_high = feature_high;
+        fis.count = ((tag as u16) << 3).to_le();
+        if is_fua {
+            fis.device |= Self::DEVICE_FUA;
+        }
+        fis
+    }
+
+    /// Creates a FLUSH CACHE EXT command.
+    pub(crate) fn flush() -> Self {
+        Self::new(ata_opcode::FLUSH_CACHE_EXT)
+    }
+
+    fn set_lba(&mut self, lba: u64) {
+        let bytes = lba.to_le_bytes();
+        self.lba_low.copy_from_slice(&bytes[0..3]);
+        self.lba_high.copy_from_slice(&bytes[3..6]);
+        self.device = Self::DEVICE_LBA;
+    }
+}
+
+/// A physical region descriptor, which describes a piece of the data buffer.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(crate) struct Prd {
+    dba: u64,
+    _reserved: u32,
+    /// The byte count minus one in bits 21:0.
+    dbc: u32,
+}
+
+impl Prd {
+    /// Creates a descriptor for the buffer at `daddr`.
+    ///
+    /// The size must be even and not exceed [`MAX_PRD_SIZE`].
+    pub(crate) fn new(daddr: u64, size: usize) -> Self {
+        Self {
+            dba: daddr,
+            _reserved: 0,
+            dbc: (size - 1) as u32,
+        }
+    }
+}
+
+/// The data returned by the IDENTIFY DEVICE command.
+#[derive(Debug)]
+pub(crate) struct IdentifyData {
+    words: [u16; 256],
+}
+
+impl IdentifyData {
+    pub(crate) fn new(words: [u16; 256]) -> Self {
+        Self { words }
+    }
+
+    /// Returns the model number.
+    pub(crate) fn model(&self) -> String {
+        // Each word contains two characters, with the first one in the high byte.
+        let bytes = self.words[27..47]
+            .iter()
+            .flat_map(|word| word.to_be_bytes())
+            .collect::<Vec<_>>();
+        String::from_utf8_lossy(&bytes).trim().into()
+    }
+
+    /// Returns whether the 48-bit LBA commands are supported.
+    pub(crate) fn supports_lba48(&self) -> bool {
+        self.words[83] & (1 << 10) != 0
+    }
+
+    /// Returns the number of the logical blocks that are addressable by the 48-bit LBA commands.
+    pub(crate) fn nr_blocks(&self) -> u64 {
+        self.words[100..104]
+            .iter()
+            .rev()
+            .fold(0, |nr_blocks, word| (nr_blocks << 16) | *word as u64)
+    }
+
+    /// Returns the size of a logical block in bytes.
+    pub(crate) fn block_size(&self) -> usize {
+        let word = self.words[106];
+        // Bit 15 is cleared and bit 14 is set if the word is valid. Bit 12 is set if the logical
+        // block is longer than 256 words.
+        if word & 0xC000 != 0x4000 || word & (1 << 12) == 0 {
+            return 512;
+        }
+
+        let nr_words = self.words[117] as usize | ((self.words[118] as usize) << 16);
+        nr_words * 2
+    }
+
+    /// Returns the maximum queue depth if the native command queuing (NCQ) is supported.
+    pub(crate) fn ncq_depth(&self) -> Option<usize> {
+        if self.words[76] & (1 << 8) == 0 {
+            return None;
+        }
+        Some((self.words[75] & 0x1F) as usize + 1)
+    }
+
+    /// Returns whether the volatile write cache is enabled.
+    pub(crate) fn has_write_cache(&self) -> bool {
+        self.words[85] & (1 << 5) != 0
+    }
+}
@@ -0,0 +1,189 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{sync::Arc, vec::Vec};
+
+use aster_pci::{
+    capability::{CapabilityData, msi::CapabilityMsiData, msix::CapabilityMsixData},
+    cfg_space::{Bar, Command},
+    common_device::PciCommonDevice,
+};
+use log::{info, warn};
+use ostd::{arch::trap::TrapFrame, io::IoMem, irq::IrqLine, mm::VmIoOnce};
+
+use crate::{disk::AhciDisk, hotplug, port::Port};
+
+/// The offsets of the generic host control registers.
+mod reg {
+    /// Host Capabilities.
+    pub(super) const CAP: usize = 0x00;
+    /// Global Host Control.
+    pub(super) const GHC: usize = 0x04;
+    /// Interrupt Status.
+    pub(super) const IS: usize = 0x08;
+    /// Ports Implemented.
+    pub(super) const PI: usize = 0x0C;
+    /// Version.
+    pub(super) const VS: usize = 0x10;
+}
+
+/// The Interrupt Enable bit of the Global Host Control register.
+const GHC_IE: u32 = 1 << 1;
+/// The AHCI Enable bit of the Global Host Control register.
+const GHC_AE: u32 = 1 << 31;
+
+/// The offset of the registers of the first port.
+const PORT_REGS_OFFSET: usize = 0x100;
+/// The size of the registers of each port.
+const PORT_REGS_SIZE: usize = 0x80;
+
+/// An error that occurs when initializing an AHCI controller or a port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AhciError {
+    /// The registers are not mapped by a memory BAR.
+    NoRegisters,
+    /// The controller supports neither MSI-X nor MSI.
+    NoInterrupt,
+    /// The controller cannot access the memory above 4 GiB, where the DMA buffers are allocated.
+    UnsupportedDma,
+    /// The port or the device does not respond in time.
+    Timeout,
+    /// No device is attached to the port.
+    NoDevice,
+    /// The device is not a SATA disk that the driver supports.
+    UnsupportedDevice,
+    /// There are too many disks to be named.
+    TooManyDisks,
+    /// A command fails with the task file data.
+    CommandFailed(u32),
+}
+
+/// The capabilities of an AHCI controller.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct HbaCapabilities {
+    /// The number of the command slots of each port.
+    pub(crate) nr_slots: usize,
+    pub(crate) supports_64bit_dma: bool,
+    /// Whether the native command queuing (NCQ) is supported.
+    pub(crate) supports_ncq: bool,
+    /// Whether the staggered spin-up is supported, in which case the devices must be spun up.
+    pub(crate) supports_staggered_spin_up: bool,
+}
+
+/// An AHCI controller, which is also known as a host bus adapter (HBA).
+#[derive(Debug)]
+pub(crate) struct AhciController {
+    ports: Vec<Arc<Port>>,
+    /// The interrupt capability, which keeps the interrupt handler registered.
+    _interrupt: Interrupt,
+}
+
+#[derive(Debug)]
+enum Interrupt {
+    Msix(CapabilityMsixData),
+    Msi(CapabilityMsiData),
+}
+
+impl AhciController {
+    /// Enables the AHCI mode of the controller and sets up the implemented ports.
+    pub(crate) fn init(device: &PciCommonDevice, index: u32) -> Result<Self, AhciError> {
+        // The AHCI Base Address (ABAR) is BAR 5.
+        let Some(Bar::Memory(bar)) = device.bar_manager().bar(5) else {
+            return Err(AhciError::NoRegisters);
+        };
+        let regs = bar.io_mem().clone();
+        device.write_command(device.read_command() | Command::MEMORY_SPACE | Command::BUS_MASTER);
+
+        // Disable the interrupts until the ports are set up.
+        let ghc: u32 = regs.read_once(reg::GHC).unwrap();
+        regs.write_once(reg::GHC, &((ghc | GHC_AE) & !GHC_IE))
+            .unwrap();
+
+        let cap: u32 = regs.read_once(reg::CAP).unwrap();
+        let caps = HbaCapabilities {
+            nr_slots: ((cap >> 8) & 0x1F) as usize + 1,
+            supports_64bit_dma: cap & (1 << 31) != 0,
+            supports_ncq: cap & (1 << 30) != 0,
+            supports_staggered_spin_up: cap & (1 << 27) != 0,
+        };
+        let implemented_ports: u32 = regs.read_once(reg::PI).unwrap();
+        let version: u32 = regs.read_once(reg::VS).unwrap();
+        info!(
+            "AHCI controller {}: version {:#x}, ports {:#x}, {:?}",
+            index, version, implemented_ports, caps
+        );
+
+        let mut ports = Vec::new();
+        for port_index in (0..32).filter(|i| implemented_ports & (1 << i) != 0) {
+            let offset = PORT_REGS_OFFSET + port_index * PORT_REGS_SIZE;
+            let port_regs = regs.slice(offset..offset + PORT_REGS_SIZE);
+            match Port::new(port_regs, port_index, &caps) {
+                Ok(port) => ports.push(port),
+                Err(err) => warn!("failed to set up AHCI port {}: {:?}", port_index, err),
+            }
+        }
+
+        let cloned_regs = regs.clone();
+        let cloned_ports = ports.clone();
+        let handle_irq = move |_: &TrapFrame| handle_irq(&cloned_regs, &cloned_ports);
+
+        // Prefer MSI-X, and fall back to MSI. All the ports share one vector.
+        let msix = find_capability(device, |data| match data {
+            CapabilityData::Msix(msix) => Some(msix.clone()),
+            _ => None,
+        });
+        let msi = find_capability(device, |data| match data {
+            CapabilityData::Msi(msi) => Some(msi.clone()),
+            _ => None,
+        });
+        let interrupt = if let Some(mut msix) = msix {
+            msix.set_interrupt_vector(IrqLine::alloc().unwrap(), 0);
+            msix.irq_mut(0).unwrap().on_active(handle_irq);
+            Interrupt::Msix(msix)
+        } else if let Some(mut msi) = msi {
+            msi.set_interrupt_vector(IrqLine::alloc().unwrap());
+            msi.irq_mut().unwrap().on_active(handle_irq);
+            Interrupt::Msi(msi)
+        } else {
+            return Err(AhciError::NoInterrupt);
+        };
+
+        regs.write_once(reg::IS, &u32::MAX).unwrap();
+        let ghc: u32 = regs.read_once(reg::GHC).unwrap();
+        regs.write_once(reg::GHC, &(ghc | GHC_IE)).unwrap();
+
+        Ok(Self {
+            ports,
+            _interrupt: interrupt,
+        })
+    }
+
+    /// Probes the devices attached to the ports, returning the disks.
+    pub(crate) fn probe_disks(&self) -> Vec<Arc<AhciDisk>> {
+        self.ports.iter().filter_map(|port| port.probe()).collect()
+    }
+}
+
+fn find_capability<T>(
+    device: &PciCommonDevice,
+    f: impl FnMut(&CapabilityData) -> Option<T>,
+) -> Option<T> {
+    device
+        .capabilities()
+        .iter()
+        .map(|cap| cap.capability_data())
+        .find_map(f)
+}
+
+/// Handles the interrupt of the controller by dispatching it to the ports.
+fn handle_irq(regs: &IoMem, ports: &[Arc<Port>]) {
+    let status: u32 = regs.read_once(reg::IS).unwrap();
+
+    for port in ports.iter().filter(|port| status & (1 << port.index()) != 0) {
+        if port.handle_irq() {
+            hotplug::notify(port.clone());
+        }
+    }
+
+    // The bits must be cleared after the interrupts of the ports are cleared.
+    regs.write_once(reg::IS, &status).unwrap();
+}
@@ -0,0 +1,175 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use aster_block::{
+    BlockDevice, BlockDeviceMeta, EXTENDED_DEVICE_ID_ALLOCATOR, PartitionInfo, PartitionNode,
+    SCSI_DISK_ALLOCATOR, SECTOR_SIZE, ScsiDiskSlot,
+    bio::{BioEnqueueError, BioStatus, BioType, SubmittedBio},
+    request_queue::{BioRequest, BioRequestQueue},
+};
+use device_id::DeviceId;
+use ostd::sync::SpinLock;
+
+use crate::{controller::AhciError, port::Port};
+
+/// The information of a SATA disk.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DiskInfo {
+    /// The size of the disk in logical blocks.
+    pub(crate) nr_blocks: u64,
+    /// The size of a logical block, as a power of two.
+    pub(crate) lba_shift: u32,
+    /// Whether the disk has a volatile write cache that needs flushing.
+    pub(crate) has_write_cache: bool,
+    /// Whether the writes can force the unit access (FUA).
+    pub(crate) supports_fua: bool,
+}
+
+/// A SATA disk attached to a port of an AHCI controller.
+///
+/// Like Linux, the SATA disks share the names and device IDs with the SCSI disks, e.g., `sda`.
+/// The requests of a disk should be handled by a dedicated thread (see
+/// [`AhciDisk::handle_requests`]).
+#[derive(Debug)]
+pub struct AhciDisk {
+    port: Arc<Port>,
+    info: DiskInfo,
+    /// The software staging queue.
+    queue: BioRequestQueue,
+    slot: ScsiDiskSlot,
+    is_detached: AtomicBool,
+    partitions: SpinLock<Option<Vec<Arc<PartitionNode>>>>,
+    weak_self: Weak<Self>,
+}
+
+impl AhciDisk {
+    pub(crate) fn new(port: Arc<Port>, info: DiskInfo) -> Result<Arc<Self>, AhciError> {
+        let slot = SCSI_DISK_ALLOCATOR
+            .get()
+            .unwrap()
+            .allocate()
+            .map_err(|_| AhciError::TooManyDisks)?;
+
+        Ok(Arc::new_cyclic(|weak_self| Self {
+            port,
+            info,
+            queue: BioRequestQueue::new(),
+            slot,
+            is_detached: AtomicBool::new(false),
+            partitions: SpinLock::new(None),
+            weak_self: weak_self.clone(),
+        }))
+    }
+
+    /// Dequeues a `BioRequest` from the software staging queue and
+    /// submits the request to the port.
+    ///
+    /// This method returns `false` without waiting if the disk is detached and all the
+    /// requests have been handled, in which case the thread should exit.
+    pub fn handle_requests(&self) -> bool {
+        let Some(request) = self.queue.dequeue_unless_closed() else {
+            return false;
+        };
+
+        if self.is_detached.load(Ordering::Relaxed) {
+            complete_request(&request, BioStatus::IoError);
+            return true;
+        }
+
+        match request.type_() {
+            BioType::Read | BioType::Write => {
+                self.port.submit_read_write(self.info.lba_shift, request)
+            }
+            BioType::Flush if self.info.has_write_cache => self.port.submit_flush(request),
+            BioType::Flush => complete_request(&request, BioStatus::Complete),
+            BioType::Discard => complete_request(&request, BioStatus::NotSupported),
+        }
+        true
+    }
+
+    /// Marks the disk as detached, so that the new requests fail.
+    pub(crate) fn mark_detached(&self) {
+        self.is_detached.store(true, Ordering::Relaxed);
+        self.queue.close();
+    }
+}
+
+fn complete_request(request: &BioRequest, status: BioStatus) {
+    request.bios().for_each(|bio| bio.complete(status));
+}
+
+impl BlockDevice for AhciDisk {
+    fn enqueue(&self, bio: SubmittedBio) -> Result<(), BioEnqueueError> {
+        self.queue.enqueue(bio)
+    }
+
+    fn metadata(&self) -> BlockDeviceMeta {
+        BlockDeviceMeta {
+            max_nr_segments_per_bio: self.queue.max_nr_segments_per_bio(),
+            nr_sectors: (self.info.nr_blocks << self.info.lba_shift) as usize / SECTOR_SIZE,
+            supports_fua: self.info.supports_fua,
+            // TODO: Support the DATA SET MANAGEMENT command to trim the SSDs.
+            max_discard_sectors: 0,
+        }
+    }
+
+    fn name(&self) -> &str {
+        self.slot.name()
+    }
+
+    fn id(&self) -> DeviceId {
+        self.slot.id()
+    }
+
+    fn set_partitions(&self, infos: Vec<Option<PartitionInfo>>) {
+        let old_partitions = self.partitions.lock().take();
+        for partition in old_partitions.into_iter().flatten() {
+            let _ = aster_block::unregister(partition.id());
+            // This does nothing if the ID is not an extended device ID.
+            EXTENDED_DEVICE_ID_ALLOCATOR
+                .get()
+                .unwrap()
+                .release(partition.id());
+        }
+
+        let mut new_partitions = Vec::new();
+        for (index, info_opt) in infos.iter().enumerate() {
+            let Some(info) = info_opt else {
+                continue;
+            };
+
+            let number = index as u32 + 1;
+            let id = self.slot.allocate_partition_id(number);
+            let name = aster_block::partition_name(self.name(), number);
+            let device = self.weak_self.upgrade().unwrap();
+
+            let partition = Arc::new(PartitionNode::new(id, name, device, number, *info));
+            new_partitions.push(partition);
+        }
+
+        for partition in new_partitions.iter() {
+            let _ = aster_block::register(partition.clone());
+        }
+
+        *self.partitions.lock() = Some(new_partitions);
+    }
+
+    fn partitions(&self) -> Option<Vec<Arc<dyn BlockDevice>>> {
+        let partitions = self.partitions.lock();
+        let devices = partitions
+            .as_ref()?
+            .iter()
+            .map(|p| p.clone() as Arc<dyn BlockDevice>)
+            .collect();
+        Some(devices)
+    }
+
+    fn request_queue(&self) -> Option<&BioRequestQueue> {
+        Some(&self.queue)
+    }
+}
@@ -0,0 +1,69 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{collections::vec_deque::VecDeque, sync::Arc};
+
+use aster_pci::{
+    PCI_BUS, PciDeviceId,
+    bus::{PciDevice, PciDriver},
+    common_device::PciCommonDevice,
+};
+use ostd::{bus::BusProbeError, sync::SpinLock};
+use spin::Once;
+
+pub(crate) static AHCI_PCI_DRIVER: Once<Arc<AhciPciDriver>> = Once::new();
+
+pub(crate) fn init() {
+    AHCI_PCI_DRIVER.call_once(|| Arc::new(AhciPciDriver::new()));
+    PCI_BUS
+        .lock()
+        .register_driver(AHCI_PCI_DRIVER.get().unwrap().clone());
+}
+
+/// The PCI driver that claims the AHCI controllers.
+///
+/// The claimed controllers are initialized later by the component.
+#[derive(Debug)]
+pub(crate) struct AhciPciDriver {
+    devices: SpinLock<VecDeque<PciCommonDevice>>,
+}
+
+impl AhciPciDriver {
+    fn new() -> Self {
+        Self {
+            devices: SpinLock::new(VecDeque::new()),
+        }
+    }
+
+    pub(crate) fn pop_device(&self) -> Option<PciCommonDevice> {
+        self.devices.lock().pop_front()
+    }
+}
+
+impl PciDriver for AhciPciDriver {
+    fn probe(
+        &self,
+        device: PciCommonDevice,
+    ) -> Result<Arc<dyn PciDevice>, (BusProbeError, PciCommonDevice)> {
+        // Mass storage controller, Serial ATA controller, AHCI 1.0
+        const AHCI_CLASS: (u8, u8, u8) = (0x01, 0x06, 0x01);
+
+        let device_id = *device.device_id();
+        if (device_id.class, device_id.subclass, device_id.prog_if) != AHCI_CLASS {
+            return Err((BusProbeError::DeviceNotMatch, device));
+        }
+        self.devices.lock().push_back(device);
+
+        Ok(Arc::new(AhciPciDevice { device_id }))
+    }
+}
+
+#[derive(Debug)]
+struct AhciPciDevice {
+    device_id: PciDeviceId,
+}
+
+impl PciDevice for AhciPciDevice {
+    fn device_id(&self) -> PciDeviceId {
+        self.device_id
+    }
+}
@@ -0,0 +1,41 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{collections::vec_deque::VecDeque, sync::Arc};
+
+use ostd::sync::{SpinLock, WaitQueue};
+
+use crate::{disk::AhciDisk, port::Port};
+
+/// An event that a disk is attached to or detached from a port.
+#[derive(Debug)]
+pub enum HotplugEvent {
+    /// A disk is attached and should be registered.
+    Attached(Arc<AhciDisk>),
+    /// A disk is detached and should be unregistered.
+    Detached(Arc<AhciDisk>),
+}
+
+/// The ports whose events are not handled yet.
+static PENDING_PORTS: SpinLock<VecDeque<Arc<Port>>> = SpinLock::new(VecDeque::new());
+static WAIT_QUEUE: WaitQueue = WaitQueue::new();
+
+/// Notifies the hot-plug thread that the port has events to handle.
+///
+/// This function is called in the interrupt context.
+pub(crate) fn notify(port: Arc<Port>) {
+    PENDING_PORTS.lock().push_back(port);
+    WAIT_QUEUE.wake_all();
+}
+
+/// Waits for the next hot-plug event.
+///
+/// This function should be called repeatedly by a dedicated thread, which also recovers the ports
+/// from the errors. The thread may wait for the devices for seconds.
+pub fn wait_for_hotplug_event() -> HotplugEvent {
+    loop {
+        let port = WAIT_QUEUE.wait_until(|| PENDING_PORTS.disable_irq().lock().pop_front());
+        if let Some(event) = port.handle_events() {
+            return event;
+        }
+    }
+}
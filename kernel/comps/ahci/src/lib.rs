@@ -0,0 +1,71 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The AHCI driver of Asterinas.
+//!
+//! The driver claims the AHCI controllers on the PCI bus. Each implemented port of a controller
+//! may have a SATA disk attached, which is registered as a block device named like the SCSI
+//! disks (e.g., `sda`). The disks that support the native command queuing (NCQ) have multiple
+//! outstanding commands. The disks can be plugged and unplugged at runtime, and the events are
+//! reported by [`wait_for_hotplug_event`].
+//!
+//! Reference: Serial ATA AHCI Specification, Revision 1.3.1.
+
+#![no_std]
+#![deny(unsafe_code)]
+
+extern crate alloc;
+#[macro_use]
+extern crate ostd_pod;
+
+mod command;
+mod controller;
+mod disk;
+mod driver;
+mod hotplug;
+mod port;
+
+use alloc::vec::Vec;
+
+use aster_block::bio::bio_segment_pool_init;
+use component::{ComponentInitError, init_component};
+use log::error;
+use ostd::sync::SpinLock;
+
+pub use self::{
+    disk::AhciDisk,
+    hotplug::{HotplugEvent, wait_for_hotplug_event},
+};
+use self::{controller::AhciController, driver::AHCI_PCI_DRIVER};
+
+/// The initialized controllers, which must be kept to keep the interrupt handlers registered.
+static CONTROLLERS: SpinLock<Vec<AhciController>> = SpinLock::new(Vec::new());
+
+#[init_component]
+fn ahci_init() -> Result<(), ComponentInitError> {
+    driver::init();
+
+    let mut index = 0;
+    while let Some(device) = AHCI_PCI_DRIVER.get().unwrap().pop_device() {
+        let controller = match AhciController::init(&device, index) {
+            Ok(controller) => controller,
+            Err(err) => {
+                error!(
+                    "failed to initialize the AHCI controller at {:?}: {:?}",
+                    device.location(),
+                    err
+                );
+                continue;
+            }
+        };
+        index += 1;
+
+        for disk in controller.probe_disks() {
+            aster_block::register(disk).unwrap();
+        }
+        CONTROLLERS.lock().push(controller);
+
+        bio_segment_pool_init();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,795 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{sync::Arc, vec, vec::Vec};
+use core::{
+    hint::spin_loop,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use aster_block::{
+    BlockDevice, SECTOR_SIZE,
+    bio::{BioFlags, BioStatus, BioType},
+    request_queue::BioRequest,
+};
+use log::{info, warn};
+use ostd::{
+    io::IoMem,
+    mm::{HasDaddr, HasSize, PAGE_SIZE, VmIo, VmIoOnce, dma::DmaCoherent},
+    sync::{Mutex, SpinLock, WaitQueue},
+    timer::Jiffies,
+};
+
+use crate::{
+    command::{
+        CMD_TABLE_SIZE, CommandHeader, IdentifyData, MAX_PRD_SIZE, MAX_PRDS, PRDT_OFFSET, Prd,
+        RegisterFis,
+    },
+    controller::{AhciError, HbaCapabilities},
+    disk::{AhciDisk, DiskInfo},
+    hotplug::HotplugEvent,
+};
+
+/// The offsets of the port registers.
+mod reg {
+    /// Command List Base Address.
+    pub(super) const CLB: usize = 0x00;
+    /// FIS Base Address.
+    pub(super) const FB: usize = 0x08;
+    /// Interrupt Status.
+    pub(super) const IS: usize = 0x10;
+    /// Interrupt Enable.
+    pub(super) const IE: usize = 0x14;
+    /// Command and Status.
+    pub(super) const CMD: usize = 0x18;
+    /// Task File Data.
+    pub(super) const TFD: usize = 0x20;
+    /// Signature.
+    pub(super) const SIG: usize = 0x24;
+    /// SATA Status.
+    pub(super) const SSTS: usize = 0x28;
+    /// SATA Control.
+    pub(super) const SCTL: usize = 0x2C;
+    /// SATA Error.
+    pub(super) const SERR: usize = 0x30;
+    /// SATA Active.
+    pub(super) const SACT: usize = 0x34;
+    /// Command Issue.
+    pub(super) const CI: usize = 0x38;
+}
+
+/// The Start bit of the Command and Status register.
+const CMD_ST: u32 = 1 << 0;
+/// The Spin-Up Device bit of the Command and Status register.
+const CMD_SUD: u32 = 1 << 1;
+/// The FIS Receive Enable bit of the Command and Status register.
+const CMD_FRE: u32 = 1 << 4;
+/// The FIS Receive Running bit of the Command and Status register.
+const CMD_FR: u32 = 1 << 14;
+/// The Command List Running bit of the Command and Status register.
+const CMD_CR: u32 = 1 << 15;
+
+/// The interrupts that indicate the completions of the commands, including the Device to Host
+/// Register FIS, PIO Setup FIS, DMA Setup FIS, Set Device Bits FIS, and Descriptor Processed
+/// interrupts.
+const IS_COMPLETION: u32 = 0b10_1111;
+/// The interrupts that indicate the changes of the device presence, including the Port Connect
+/// Change and PhyRdy Change interrupts.
+const IS_HOTPLUG: u32 = (1 << 6) | (1 << 22);
+/// The interrupts that indicate the errors, including the Interface Fatal Error, Host Bus Data
+/// Error, Host Bus Fatal Error, and Task File Error interrupts.
+const IS_ERROR: u32 = (1 << 27) | (1 << 28) | (1 << 29) | (1 << 30);
+
+/// The Error bit of the Task File Data register.
+const TFD_ERR: u32 = 1 << 0;
+/// The Data Transfer Requested bit of the Task File Data register.
+const TFD_DRQ: u32 = 1 << 3;
+/// The Busy bit of the Task File Data register.
+const TFD_BSY: u32 = 1 << 7;
+
+/// The mask of the Device Detection field of the SATA Status and SATA Control registers.
+const DET_MASK: u32 = 0xF;
+/// The Device Detection value that indicates the communication with the device is established.
+const SSTS_DET_ESTABLISHED: u32 = 3;
+/// The Device Detection value that performs the COMRESET.
+const SCTL_DET_COMRESET: u32 = 1;
+
+/// The signature of the SATA disks.
+const SIG_ATA: u32 = 0x0000_0101;
+
+/// The offset of the received FIS area in the port memory, which follows the command list.
+const RECEIVED_FIS_OFFSET: usize = 1024;
+
+/// The maximum number of bytes that a command can transfer.
+///
+/// It is a multiple of all the supported logical block sizes, and it does not exceed the limit
+/// of the 16-bit block count with 512-byte logical blocks.
+const MAX_TRANSFER_SIZE: usize = 16 << 20;
+
+const LINK_TIMEOUT: Duration = Duration::from_secs(1);
+const READY_TIMEOUT: Duration = Duration::from_secs(10);
+const ENGINE_TIMEOUT: Duration = Duration::from_millis(500);
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+/// The duration for which the COMRESET is sent, which must be at least 1 ms.
+const COMRESET_DURATION: Duration = Duration::from_millis(1);
+
+/// A port of an AHCI controller, to which at most one SATA disk is attached.
+#[derive(Debug)]
+pub(crate) struct Port {
+    index: usize,
+    /// The registers of the port.
+    regs: IoMem,
+    /// The command list, followed by the received FIS area.
+    memory: DmaCoherent,
+    /// The command tables, one for each command slot.
+    cmd_tables: DmaCoherent,
+    caps: HbaCapabilities,
+    state: SpinLock<PortState>,
+    /// The waiters for free command slots.
+    wait_queue: WaitQueue,
+    /// The disk attached to the port.
+    disk: Mutex<Option<Arc<AhciDisk>>>,
+}
+
+#[derive(Debug)]
+struct PortState {
+    /// The requests of the allocated command slots.
+    slots: Vec<Option<Arc<InflightRequest>>>,
+    /// The bitmap of the command slots that have been issued to the controller.
+    issued: u32,
+    /// The number of the command slots that can be used by the queued commands.
+    depth: usize,
+    is_attached: bool,
+    /// Whether the native command queuing (NCQ) is used.
+    is_ncq: bool,
+    /// Whether a non-queued command is outstanding.
+    ///
+    /// A non-queued command cannot be issued with any other commands.
+    is_exclusive: bool,
+    /// Whether a non-queued command is waiting for the outstanding commands to complete.
+    is_draining: bool,
+    /// Whether the port is stopped because of an error, and waits for the recovery.
+    is_recovering: bool,
+}
+
+/// A request that may be split into multiple commands.
+#[derive(Debug)]
+struct InflightRequest {
+    request: BioRequest,
+    nr_pending_commands: AtomicUsize,
+    has_failed: AtomicBool,
+}
+
+/// A command that transfers data.
+#[derive(Debug)]
+struct Transfer {
+    prds: Vec<Prd>,
+    nr_bytes: usize,
+}
+
+impl Port {
+    /// Creates a port with the registers, and enables the FIS receive DMA engine of it.
+    pub(crate) fn new(
+        regs: IoMem,
+        index: usize,
+        caps: &HbaCapabilities,
+    ) -> Result<Arc<Self>, AhciError> {
+        let memory = DmaCoherent::alloc(1, true).unwrap();
+        let cmd_tables = DmaCoherent::alloc(caps.nr_slots, true).unwrap();
+        let dma_end = (memory.daddr() + memory.size())
+            .max(cmd_tables.daddr() + cmd_tables.size());
+        if !caps.supports_64bit_dma && dma_end as u64 > 1 << 32 {
+            return Err(AhciError::UnsupportedDma);
+        }
+
+        let port = Self {
+            index,
+            regs,
+            memory,
+            cmd_tables,
+            caps: *caps,
+            state: SpinLock::new(PortState {
+                slots: vec![None; caps.nr_slots],
+                issued: 0,
+                depth: 1,
+                is_attached: false,
+                is_ncq: false,
+                is_exclusive: false,
+                is_draining: false,
+                is_recovering: false,
+            }),
+            wait_queue: WaitQueue::new(),
+            disk: Mutex::new(None),
+        };
+
+        // The DMA engines must be stopped before the addresses are changed.
+        port.stop()?;
+        port.write(reg::CMD, port.read(reg::CMD) & !CMD_FRE);
+        if !wait_for(|| port.read(reg::CMD) & CMD_FR == 0, ENGINE_TIMEOUT) {
+            return Err(AhciError::Timeout);
+        }
+
+        port.write_u64(reg::CLB, port.memory.daddr() as u64);
+        port.write_u64(reg::FB, (port.memory.daddr() + RECEIVED_FIS_OFFSET) as u64);
+        port.write(reg::SERR, u32::MAX);
+        port.write(reg::IS, u32::MAX);
+
+        let mut cmd = port.read(reg::CMD) | CMD_FRE;
+        if caps.supports_staggered_spin_up {
+            cmd |= CMD_SUD;
+        }
+        port.write(reg::CMD, cmd);
+        port.write(reg::IE, IS_HOTPLUG);
+
+        Ok(Arc::new(port))
+    }
+
+    /// Returns the index of the port in the controller.
+    pub(crate) fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Probes the device attached to the port when booting, returning the disk if found.
+    pub(crate) fn probe(self: &Arc<Self>) -> Option<Arc<AhciDisk>> {
+        if !self.is_device_present() {
+            return None;
+        }
+
+        let mut disk = self.disk.lock();
+        match self.attach() {
+            Ok(new_disk) => {
+                *disk = Some(new_disk.clone());
+                Some(new_disk)
+            }
+            Err(err) => {
+                warn!("failed to attach the device at AHCI port {}: {:?}", self.index, err);
+                None
+            }
+        }
+    }
+
+    /// Handles the events of the port, which are raised by [`Self::handle_irq`].
+    ///
+    /// This method should be called in the hot-plug thread, since it may wait for the device.
+    pub(crate) fn handle_events(self: &Arc<Self>) -> Option<HotplugEvent> {
+        if self.state.disable_irq().lock().is_recovering {
+            self.recover();
+        }
+
+        let mut disk = self.disk.lock();
+        match (disk.is_some(), self.is_device_present()) {
+            (true, false) => {
+                let old_disk = disk.take().unwrap();
+                old_disk.mark_detached();
+                self.detach();
+                info!("AHCI port {}: disk {} is detached", self.index, old_disk.name());
+                Some(HotplugEvent::Detached(old_disk))
+            }
+            (false, true) => match self.attach() {
+                Ok(new_disk) => {
+                    *disk = Some(new_disk.clone());
+                    Some(HotplugEvent::Attached(new_disk))
+                }
+                Err(err) => {
+                    warn!("failed to attach the device at AHCI port {}: {:?}", self.index, err);
+                    None
+                }
+            },
+            _ => None,
+        }
+    }
+
+    /// Handles the interrupt of the port.
+    ///
+    /// This method returns whether the port needs the attention of the hot-plug thread, i.e., a
+    /// device may be plugged or unplugged, or the port should be recovered from an error.
+    pub(crate) fn handle_irq(&self) -> bool {
+        let status = self.read(reg::IS) & self.read(reg::IE);
+        // The Port Connect Change interrupt can only be cleared by clearing the SATA errors.
+        if status & IS_HOTPLUG != 0 {
+            self.write(reg::SERR, u32::MAX);
+        }
+        self.write(reg::IS, status);
+
+        let finished = {
+            let mut state = self.state.lock();
+            if status & IS_ERROR != 0 {
+                warn!(
+                    "AHCI port {}: interrupt status {:#x}, task file data {:#x}",
+                    self.index,
+                    status,
+                    self.read(reg::TFD)
+                );
+                // Stop processing the commands and leave the recovery to the hot-plug thread.
+                self.write(reg::CMD, self.read(reg::CMD) & !CMD_ST);
+                state.is_recovering = true;
+                state.fail_issued()
+            } else {
+                let active = self.read(reg::CI) | self.read(reg::SACT);
+                state.complete_issued(active)
+            }
+        };
+
+        self.wait_queue.wake_all();
+        finished.iter().for_each(|inflight| inflight.complete());
+
+        status & (IS_ERROR | IS_HOTPLUG) != 0
+    }
+
+    /// Submits a read or write request.
+    ///
+    /// The request is split into multiple commands if it is too big or has too many segments.
+    /// This method will wait if no command slots are available.
+    pub(crate) fn submit_read_write(&self, lba_shift: u32, request: BioRequest) {
+        let is_write = request.type_() == BioType::Write;
+        let is_fua = request.flags().contains(BioFlags::FUA);
+
+        let sector_shift = lba_shift - SECTOR_SIZE.ilog2();
+        let start_sector = request.sid_range().start.to_raw();
+        let transfers = if start_sector & ((1 << sector_shift) - 1) == 0 {
+            self.split_into_transfers(&request, lba_shift)
+        } else {
+            None
+        };
+        let Some(transfers) = transfers else {
+            warn!("unaligned or unaddressable AHCI request: {:?}", request);
+            request.bios().for_each(|bio| bio.complete(BioStatus::IoError));
+            return;
+        };
+
+        let inflight = Arc::new(InflightRequest::new(request, transfers.len()));
+
+        let mut lba = start_sector >> sector_shift;
+        for transfer in transfers {
+            let nr_blocks = (transfer.nr_bytes >> lba_shift) as u32;
+            let Some((slot, is_queued)) = self.alloc_slot(true, &inflight) else {
+                if inflight.finish_command(true) {
+                    inflight.complete();
+                }
+                continue;
+            };
+
+            let fis = if is_queued {
+                RegisterFis::read_write_fpdma(is_write, is_fua, lba, nr_blocks, slot as u8)
+            } else {
+                RegisterFis::read_write_dma(is_write, lba, nr_blocks)
+            };
+            self.write_command(slot, &fis, &transfer.prds, is_write);
+            self.issue(slot, is_queued);
+
+            lba += nr_blocks as u64;
+        }
+    }
+
+    /// Submits a flush request.
+    pub(crate) fn submit_flush(&self, request: BioRequest) {
+        let inflight = Arc::new(InflightRequest::new(request, 1));
+
+        let Some((slot, _)) = self.alloc_slot(false, &inflight) else {
+            if inflight.finish_command(true) {
+                inflight.complete();
+            }
+            return;
+        };
+        self.write_command(slot, &RegisterFis::flush(), &[], false);
+        self.issue(slot, false);
+    }
+
+    /// Identifies the device attached to the port and creates a disk for it.
+    fn attach(self: &Arc<Self>) -> Result<Arc<AhciDisk>, AhciError> {
+        if !wait_for(
+            || self.read(reg::SSTS) & DET_MASK == SSTS_DET_ESTABLISHED,
+            LINK_TIMEOUT,
+        ) {
+            return Err(AhciError::NoDevice);
+        }
+        // Clear the errors that occur when establishing the link.
+        self.write(reg::SERR, u32::MAX);
+        if !wait_for(
+            || self.read(reg::TFD) & (TFD_BSY | TFD_DRQ) == 0,
+            READY_TIMEOUT,
+        ) {
+            return Err(AhciError::Timeout);
+        }
+        if self.read(reg::SIG) != SIG_ATA {
+            return Err(AhciError::UnsupportedDevice);
+        }
+
+        self.start();
+        let identify = self.identify().inspect_err(|_| {
+            let _ = self.stop();
+        })?;
+
+        let block_size = identify.block_size();
+        if !identify.supports_lba48()
+            || !block_size.is_power_of_two()
+            || !(SECTOR_SIZE..=PAGE_SIZE).contains(&block_size)
+        {
+            let _ = self.stop();
+            return Err(AhciError::UnsupportedDevice);
+        }
+        let ncq_depth = identify
+            .ncq_depth()
+            .filter(|_| self.caps.supports_ncq)
+            .map(|depth| depth.min(self.caps.nr_slots));
+        let info = DiskInfo {
+            nr_blocks: identify.nr_blocks(),
+            lba_shift: block_size.ilog2(),
+            has_write_cache: identify.has_write_cache(),
+            // Only the queued commands have the FUA bit, so all the commands must be queued.
+            supports_fua: ncq_depth.is_some(),
+        };
+
+        info!(
+            "AHCI port {}: {}, {} blocks of {} bytes, NCQ depth {:?}",
+            self.index,
+            identify.model(),
+            info.nr_blocks,
+            block_size,
+            ncq_depth
+        );
+
+        {
+            let mut state = self.state.disable_irq().lock();
+            state.is_attached = true;
+            state.is_ncq = ncq_depth.is_some();
+            state.depth = ncq_depth.unwrap_or(1);
+            state.is_draining = false;
+        }
+        self.write(reg::IS, !IS_HOTPLUG);
+        self.write(reg::IE, IS_COMPLETION | IS_ERROR | IS_HOTPLUG);
+
+        AhciDisk::new(self.clone(), info).inspect_err(|_| self.detach())
+    }
+
+    /// Stops the port and fails the outstanding commands after the device is detached.
+    fn detach(&self) {
+        let _ = self.stop();
+        self.write(reg::IE, IS_HOTPLUG);
+
+        let finished = {
+            let mut state = self.state.disable_irq().lock();
+            state.is_attached = false;
+            state.is_ncq = false;
+            state.fail_issued()
+        };
+
+        self.wait_queue.wake_all();
+        finished.iter().for_each(|inflight| inflight.complete());
+    }
+
+    /// Recovers the port from an error.
+    ///
+    /// The device is reset, so that the error condition is cleared even if it is reported by a
+    /// queued command.
+    fn recover(&self) {
+        let result = self.stop().and_then(|()| {
+            self.write(reg::SERR, u32::MAX);
+            self.write(reg::IS, !IS_HOTPLUG);
+            self.reset_link()?;
+            self.start();
+            Ok(())
+        });
+
+        let mut state = self.state.disable_irq().lock();
+        state.is_recovering = false;
+        if let Err(err) = result {
+            warn!("failed to recover AHCI port {}: {:?}", self.index, err);
+            state.is_attached = false;
+        }
+        drop(state);
+
+        self.wait_queue.wake_all();
+    }
+
+    /// Resets the link with the COMRESET, which also resets the device.
+    fn reset_link(&self) -> Result<(), AhciError> {
+        let sctl = self.read(reg::SCTL) & !DET_MASK;
+        self.write(reg::SCTL, sctl | SCTL_DET_COMRESET);
+        // Keep sending the COMRESET for a while.
+        wait_for(|| false, COMRESET_DURATION);
+        self.write(reg::SCTL, sctl);
+
+        if !wait_for(
+            || self.read(reg::SSTS) & DET_MASK == SSTS_DET_ESTABLISHED,
+            LINK_TIMEOUT,
+        ) {
+            return Err(AhciError::NoDevice);
+        }
+        self.write(reg::SERR, u32::MAX);
+        if !wait_for(
+            || self.read(reg::TFD) & (TFD_BSY | TFD_DRQ) == 0,
+            READY_TIMEOUT,
+        ) {
+            return Err(AhciError::Timeout);
+        }
+
+        Ok(())
+    }
+
+    /// Executes the IDENTIFY DEVICE command and polls for its completion.
+    ///
+    /// The interrupts of the command completions must be disabled.
+    fn identify(&self) -> Result<IdentifyData, AhciError> {
+        let buf = DmaCoherent::alloc(1, true).unwrap();
+        let prd = Prd::new(buf.daddr() as u64, 512);
+        self.write_command(0, &RegisterFis::identify(), &[prd], false);
+        self.write(reg::CI, 1);
+
+        let is_completed = wait_for(
+            || self.read(reg::CI) & 1 == 0 || self.read(reg::IS) & IS_ERROR != 0,
+            COMMAND_TIMEOUT,
+        );
+        if !is_completed {
+            return Err(AhciError::Timeout);
+        }
+        let tfd = self.read(reg::TFD);
+        if self.read(reg::IS) & IS_ERROR != 0 || tfd & TFD_ERR != 0 {
+            self.write(reg::IS, IS_ERROR);
+            return Err(AhciError::CommandFailed(tfd));
+        }
+
+        Ok(IdentifyData::new(buf.read_val(0).unwrap()))
+    }
+
+    /// Allocates a command slot for the request, waiting if no command slots are available.
+    ///
+    /// The command is a queued command if `can_queue` is true and NCQ is used, which is
+    /// indicated by the returned boolean. This method returns `None` if the disk is detached.
+    fn alloc_slot(
+        &self,
+        can_queue: bool,
+        inflight: &Arc<InflightRequest>,
+    ) -> Option<(usize, bool)> {
+        self.wait_queue.wait_until(|| {
+            let mut state = self.state.disable_irq().lock();
+            if !state.is_attached {
+                return Some(None);
+            }
+
+            let is_queued = can_queue && state.is_ncq;
+            let slot = state.try_alloc(is_queued)?;
+            state.slots[slot] = Some(inflight.clone());
+            Some(Some((slot, is_queued)))
+        })
+    }
+
+    /// Issues the command in the allocated command slot.
+    fn issue(&self, slot: usize, is_queued: bool) {
+        let mut state = self.state.disable_irq().lock();
+
+        // The port may be detached or stopped because of an error after the slot is allocated.
+        if !state.is_attached || state.is_recovering {
+            let inflight = state.release(slot);
+            drop(state);
+
+            self.wait_queue.wake_all();
+            if inflight.finish_command(true) {
+                inflight.complete();
+            }
+            return;
+        }
+
+        state.issued |= 1 << slot;
+        if is_queued {
+            self.write(reg::SACT, 1 << slot);
+        }
+        self.write(reg::CI, 1 << slot);
+    }
+
+    fn write_command(&self, slot: usize, fis: &RegisterFis, prds: &[Prd], is_write: bool) {
+        let table_offset = slot * CMD_TABLE_SIZE;
+        self.cmd_tables.write_val(table_offset, fis).unwrap();
+        self.cmd_tables
+            .write_slice(table_offset + PRDT_OFFSET, prds)
+            .unwrap();
+
+        let ctba = (self.cmd_tables.daddr() + table_offset) as u64;
+        let header = CommandHeader::new(ctba, prds.len(), is_write);
+        self.memory
+            .write_val(slot * size_of::<CommandHeader>(), &header)
+            .unwrap();
+    }
+
+    /// Splits the segments of the request into transfers.
+    ///
+    /// This method returns `None` if a transfer would not be a multiple of the logical block
+    /// size, or a segment is not addressable by the controller.
+    fn split_into_transfers(
+        &self,
+        request: &BioRequest,
+        lba_shift: u32,
+    ) -> Option<Vec<Transfer>> {
+        let block_mask = (1 << lba_shift) - 1;
+        let mut transfers: Vec<Transfer> = Vec::new();
+
+        let dma_slices = request
+            .bios()
+            .flat_map(|bio| bio.segments().iter())
+            .map(|segment| segment.inner_dma_slice());
+        for dma_slice in dma_slices {
+            let mut addr = dma_slice.daddr() as u64;
+            let end = addr + dma_slice.size() as u64;
+            if !self.caps.supports_64bit_dma && end > 1 << 32 {
+                return None;
+            }
+
+            while addr < end {
+                let is_full = transfers.last().is_none_or(|transfer| {
+                    transfer.prds.len() == MAX_PRDS || transfer.nr_bytes == MAX_TRANSFER_SIZE
+                });
+                if is_full {
+                    if let Some(transfer) = transfers.last()
+                        && transfer.nr_bytes & block_mask != 0
+                    {
+                        return None;
+                    }
+                    transfers.push(Transfer {
+                        prds: Vec::new(),
+                        nr_bytes: 0,
+                    });
+                }
+
+                let transfer = transfers.last_mut().unwrap();
+                let size = ((end - addr) as usize)
+                    .min(MAX_PRD_SIZE)
+                    .min(MAX_TRANSFER_SIZE - transfer.nr_bytes);
+                transfer.prds.push(Prd::new(addr, size));
+                transfer.nr_bytes += size;
+
+                addr += size as u64;
+            }
+        }
+
+        if let Some(transfer) = transfers.last()
+            && transfer.nr_bytes & block_mask != 0
+        {
+            return None;
+        }
+        Some(transfers)
+    }
+
+    /// Stops the command list DMA engine.
+    fn stop(&self) -> Result<(), AhciError> {
+        self.write(reg::CMD, self.read(reg::CMD) & !CMD_ST);
+        if !wait_for(|| self.read(reg::CMD) & CMD_CR == 0, ENGINE_TIMEOUT) {
+            return Err(AhciError::Timeout);
+        }
+        Ok(())
+    }
+
+    /// Starts the command list DMA engine.
+    fn start(&self) {
+        self.write(reg::CMD, self.read(reg::CMD) | CMD_ST);
+    }
+
+    /// Returns whether a device is detected, regardless of whether the link is established.
+    fn is_device_present(&self) -> bool {
+        self.read(reg::SSTS) & DET_MASK != 0
+    }
+
+    fn read(&self, offset: usize) -> u32 {
+        self.regs.read_once(offset).unwrap()
+    }
+
+    fn write(&self, offset: usize, value: u32) {
+        self.regs.write_once(offset, &value).unwrap();
+    }
+
+    /// Writes a 64-bit address to a pair of the lower and upper 32-bit registers.
+    fn write_u64(&self, offset: usize, value: u64) {
+        self.write(offset, value as u32);
+        self.write(offset + 4, (value >> 32) as u32);
+    }
+}
+
+impl PortState {
+    /// Tries to allocate a command slot.
+    ///
+    /// Since a non-queued command cannot be issued with any other commands, no command slots
+    /// are allocated for the queued commands while a non-queued command is outstanding or waits
+    /// for the outstanding commands to complete.
+    fn try_alloc(&mut self, is_queued: bool) -> Option<usize> {
+        if self.is_recovering || self.is_exclusive {
+            return None;
+        }
+
+        if !is_queued {
+            if self.slots.iter().any(Option::is_some) {
+                self.is_draining = true;
+                return None;
+            }
+            self.is_draining = false;
+            self.is_exclusive = true;
+            return Some(0);
+        }
+
+        if self.is_draining {
+            return None;
+        }
+        (0..self.depth).find(|slot| self.slots[*slot].is_none())
+    }
+
+    fn release(&mut self, slot: usize) -> Arc<InflightRequest> {
+        self.issued &= !(1 << slot);
+        self.is_exclusive = false;
+        self.slots[slot].take().unwrap()
+    }
+
+    /// Releases the issued command slots that are no longer active, returning the requests that
+    /// are finished.
+    fn complete_issued(&mut self, active: u32) -> Vec<Arc<InflightRequest>> {
+        self.release_issued(self.issued & !active, false)
+    }
+
+    /// Releases all the issued command slots as failed, returning the requests that are
+    /// finished.
+    fn fail_issued(&mut self) -> Vec<Arc<InflightRequest>> {
+        self.release_issued(self.issued, true)
+    }
+
+    fn release_issued(&mut self, slots: u32, has_failed: bool) -> Vec<Arc<InflightRequest>> {
+        let mut finished = Vec::new();
+
+        for slot in (0..self.slots.len()).filter(|slot| slots & (1 << slot) != 0) {
+            let inflight = self.release(slot);
+            if inflight.finish_command(has_failed) {
+                finished.push(inflight);
+            }
+        }
+
+        finished
+    }
+}
+
+impl InflightRequest {
+    fn new(request: BioRequest, nr_commands: usize) -> Self {
+        Self {
+            request,
+            nr_pending_commands: AtomicUsize::new(nr_commands),
+            has_failed: AtomicBool::new(false),
+        }
+    }
+
+    /// Records that a command of the request is finished, returning whether all the commands
+    /// are finished.
+    fn finish_command(&self, has_failed: bool) -> bool {
+        if has_failed {
+            self.has_failed.store(true, Ordering::Relaxed);
+        }
+        self.nr_pending_commands.fetch_sub(1, Ordering::Relaxed) == 1
+    }
+
+    fn complete(&self) {
+        let status = if self.has_failed.load(Ordering::Relaxed) {
+            BioStatus::IoError
+        } else {
+            BioStatus::Complete
+        };
+
+        // Synchronize DMA mapping if read from the device
+        if status == BioStatus::Complete && self.request.type_() == BioType::Read {
+            self.request
+                .bios()
+                .flat_map(|bio| bio.segments().iter())
+                .for_each(|segment| segment.inner_dma_slice().sync_from_device().unwrap());
+        }
+
+        self.request.bios().for_each(|bio| bio.complete(status));
+    }
+}
+
+/// Waits until the condition holds, returning whether it holds before the timeout.
+fn wait_for(mut cond: impl FnMut() -> bool, timeout: Duration) -> bool {
+    let deadline = Jiffies::elapsed().as_duration() + timeout;
+
+    loop {
+        if cond() {
+            return true;
+        }
+        if Jiffies::elapsed().as_duration() > deadline {
+            return false;
+        }
+        spin_loop();
+    }
+}
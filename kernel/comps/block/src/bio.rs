@@ -4,6 +4,7 @@ use core::sync::atomic::AtomicU64;
 
 use align_ext::AlignExt;
 use aster_util::mem_obj_slice::Slice;
+use bitflags::bitflags;
 use bitvec::array::BitArray;
 use int_to_c_enum::TryFromInt;
 use ostd::{
@@ -50,6 +51,7 @@ impl Bio {
 
         let inner = Arc::new(BioInner {
             type_,
+            flags: BioFlags::empty(),
             sid_range: start_sid..start_sid + nsectors,
             sid_offset: AtomicU64::new(0),
             segments,
@@ -60,11 +62,43 @@ impl Bio {
         Self(inner)
     }
 
+    /// Constructs a new `Bio` that discards the sectors in the `sid_range`.
+    ///
+    /// A discard `Bio` has no memory segments.
+    pub fn new_discard(sid_range: Range<Sid>, complete_fn: Option<fn(&SubmittedBio)>) -> Self {
+        let inner = Arc::new(BioInner {
+            type_: BioType::Discard,
+            flags: BioFlags::empty(),
+            sid_range,
+            sid_offset: AtomicU64::new(0),
+            segments: Vec::new(),
+            complete_fn,
+            status: AtomicU32::new(BioStatus::Init as u32),
+            wait_queue: WaitQueue::new(),
+        });
+        Self(inner)
+    }
+
+    /// Sets the flags.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the `Bio` has been submitted.
+    pub fn with_flags(mut self, flags: BioFlags) -> Self {
+        Arc::get_mut(&mut self.0).unwrap().flags = flags;
+        self
+    }
+
     /// Returns the type.
     pub fn type_(&self) -> BioType {
         self.0.type_()
     }
 
+    /// Returns the flags.
+    pub fn flags(&self) -> BioFlags {
+        self.0.flags
+    }
+
     /// Returns the range of target sectors on the device.
     pub fn sid_range(&self) -> &Range<Sid> {
         self.0.sid_range()
@@ -84,6 +118,10 @@ impl Bio {
     ///
     /// Returns a `BioWaiter` to the caller to wait for its completion.
     ///
+    /// If the `Bio` has [`BioFlags::PREFLUSH`], or [`BioFlags::FUA`] that the device does not
+    /// support natively, the flags are emulated with cache flushes. In this case, this method
+    /// waits until the I/O is completed.
+    ///
     /// # Panics
     ///
     /// The caller must not submit a `Bio` more than once. Otherwise, a panic shall be triggered.
@@ -97,7 +135,18 @@ impl Bio {
         );
         assert!(result.is_ok());
 
-        if let Err(e) = block_device.enqueue(SubmittedBio(self.0.clone())) {
+        let flags = self.flags();
+        let result = if flags.contains(BioFlags::PREFLUSH)
+            || (flags.contains(BioFlags::FUA) && !block_device.metadata().supports_fua)
+        {
+            self.submit_with_flushes(block_device)
+        } else {
+            if let Some(hook) = SUBMIT_HOOK.get() {
+                hook(block_device, self);
+            }
+            block_device.enqueue(SubmittedBio(self.0.clone()))
+        };
+        if let Err(e) = result {
             // Fail to submit, revert the status.
             let result = self.0.status.compare_exchange(
                 BioStatus::Submit as u32,
@@ -138,6 +187,60 @@ impl Bio {
             }
         }
     }
+
+    /// Submits the I/O of self with the cache flushes that emulate the flags, and completes
+    /// self after all of them are done.
+    fn submit_with_flushes(&self, block_device: &dyn BlockDevice) -> Result<(), BioEnqueueError> {
+        let flags = self.flags();
+
+        if flags.contains(BioFlags::PREFLUSH) {
+            let status = block_device.sync()?;
+            if status != BioStatus::Complete {
+                SubmittedBio(self.0.clone()).complete(status);
+                return Ok(());
+            }
+        }
+
+        let native_flags = if block_device.metadata().supports_fua {
+            flags & BioFlags::FUA
+        } else {
+            BioFlags::empty()
+        };
+        let bio = Bio::new(
+            self.type_(),
+            self.sid_range().start,
+            self.segments().to_vec(),
+            None,
+        )
+        .with_flags(native_flags | BioFlags::SYNC);
+        let mut status = bio.submit_and_wait(block_device)?;
+
+        if status == BioStatus::Complete
+            && flags.contains(BioFlags::FUA)
+            && !native_flags.contains(BioFlags::FUA)
+        {
+            status = block_device.sync()?;
+        }
+
+        SubmittedBio(self.0.clone()).complete(status);
+        Ok(())
+    }
+}
+
+/// A hook that is called before a `Bio` is enqueued to a block device.
+///
+/// The hook can account and throttle the I/O of the current task (e.g., for the I/O
+/// controller of cgroups), so it may sleep. Each `Bio` that carries data is passed to the hook
+/// exactly once, even if it is emulated with the cache flushes.
+pub type BioSubmitHook = fn(&dyn BlockDevice, &Bio);
+
+static SUBMIT_HOOK: Once<BioSubmitHook> = Once::new();
+
+/// Registers the hook that is called before a `Bio` is enqueued.
+///
+/// Only the first registered hook takes effect.
+pub fn register_submit_hook(hook: BioSubmitHook) {
+    SUBMIT_HOOK.call_once(|| hook);
 }
 
 /// The error type returned when enqueueing the `Bio`.
@@ -261,6 +364,11 @@ impl SubmittedBio {
         self.0.type_()
     }
 
+    /// Returns the flags.
+    pub fn flags(&self) -> BioFlags {
+        self.0.flags
+    }
+
     /// Returns the range of target sectors on the device.
     pub fn sid_range(&self) -> &Range<Sid> {
         self.0.sid_range()
@@ -312,6 +420,8 @@ impl SubmittedBio {
 struct BioInner {
     /// The type of the I/O
     type_: BioType,
+    /// The flags that modify the I/O
+    flags: BioFlags,
     /// The logical range of target sectors on device
     sid_range: Range<Sid>,
     /// The offset of the first sector id, used to adjust the `sid_range` for partition devices
@@ -348,6 +458,7 @@ impl Debug for BioInner {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         f.debug_struct("BioInner")
             .field("type", &self.type_())
+            .field("flags", &self.flags)
             .field("sid_range", &self.sid_range())
             .field("status", &self.status())
             .field("segments", &self.segments())
@@ -370,6 +481,20 @@ pub enum BioType {
     Discard = 3,
 }
 
+bitflags! {
+    /// The flags of `Bio`.
+    pub struct BioFlags: u32 {
+        /// Flush the volatile write cache before the I/O.
+        ///
+        /// The data written by the completed I/O will be persistent before the I/O starts.
+        const PREFLUSH = 1 << 0;
+        /// Force unit access, i.e., complete the I/O only after the data are persistent.
+        const FUA = 1 << 1;
+        /// The submitter waits for the I/O, so it should be dispatched without delay.
+        const SYNC = 1 << 2;
+    }
+}
+
 /// The status of `Bio`.
 #[derive(Clone, Copy, PartialEq, Eq, Debug, TryFromInt)]
 #[repr(u32)]
@@ -1,13 +1,13 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use alloc::collections::btree_set::BTreeSet;
+use alloc::{collections::btree_set::BTreeSet, string::String};
 
 use device_id::{DeviceId, MajorId, MinorId};
 use id_alloc::IdAlloc;
 use ostd::sync::Mutex;
 use spin::Once;
 
-use crate::Error;
+use crate::{Error, partition::disk_name};
 
 /// The maximum value of the major device ID of a block device.
 ///
@@ -101,7 +101,7 @@ impl ExtendedDeviceIdAllocator {
     }
 
     /// Releases an extended device ID.
-    pub fn release(&mut self, id: DeviceId) {
+    pub fn release(&self, id: DeviceId) {
         if id.major() != self.major.get() {
             return;
         }
@@ -112,6 +112,116 @@ impl ExtendedDeviceIdAllocator {
 
 pub static EXTENDED_DEVICE_ID_ALLOCATOR: Once<ExtendedDeviceIdAllocator> = Once::new();
 
+/// The major ID of the first 16 SCSI disks.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/major.h#L22>.
+const SCSI_DISK0_MAJOR: u16 = 8;
+
+/// The number of minor IDs allocated for each SCSI disk, including the whole disk and its
+/// partitions.
+const SCSI_DISK_MINORS: u32 = 16;
+
+/// The number of SCSI disks that use the minor IDs of [`SCSI_DISK0_MAJOR`].
+const NR_SCSI_DISKS_WITH_MAJOR: u32 = 16;
+
+/// The maximum number of SCSI disks.
+const MAX_SCSI_DISKS: usize = 1024;
+
+/// An allocator for the names and the device IDs of SCSI disks.
+///
+/// Like Linux, the disks attached to all kinds of host adapters (e.g., virtio-scsi and AHCI) are
+/// named `sda`, `sdb`, and so on. So the drivers must share the allocator.
+pub struct ScsiDiskAllocator {
+    major: MajorIdOwner,
+    index_allocator: Mutex<IdAlloc>,
+}
+
+impl ScsiDiskAllocator {
+    fn new() -> Self {
+        let major = MajorId::new(SCSI_DISK0_MAJOR);
+
+        Self {
+            major: acquire_major(major).unwrap(),
+            index_allocator: Mutex::new(IdAlloc::with_capacity(MAX_SCSI_DISKS)),
+        }
+    }
+
+    /// Allocates the name and the device ID of a SCSI disk.
+    ///
+    /// The first 16 disks use the minor IDs of the SCSI disk major ID, and the other disks use
+    /// the extended device IDs.
+    pub fn allocate(&self) -> Result<ScsiDiskSlot, Error> {
+        let index = self
+            .index_allocator
+            .lock()
+            .alloc()
+            .ok_or(Error::IdExhausted)? as u32;
+
+        let id = if index < NR_SCSI_DISKS_WITH_MAJOR {
+            DeviceId::new(self.major.get(), MinorId::new(index * SCSI_DISK_MINORS))
+        } else {
+            EXTENDED_DEVICE_ID_ALLOCATOR.get().unwrap().allocate()
+        };
+
+        Ok(ScsiDiskSlot {
+            index,
+            id,
+            name: disk_name("sd", index),
+        })
+    }
+}
+
+pub static SCSI_DISK_ALLOCATOR: Once<ScsiDiskAllocator> = Once::new();
+
+/// The name and the device ID of a SCSI disk.
+///
+/// They are released when the object is dropped.
+#[derive(Debug)]
+pub struct ScsiDiskSlot {
+    index: u32,
+    id: DeviceId,
+    name: String,
+}
+
+impl ScsiDiskSlot {
+    /// Returns the device ID of the disk.
+    pub fn id(&self) -> DeviceId {
+        self.id
+    }
+
+    /// Returns the name of the disk.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Allocates the device ID of the partition of `number`.
+    ///
+    /// The ID should be released via [`EXTENDED_DEVICE_ID_ALLOCATOR`] when the partition is
+    /// removed.
+    pub fn allocate_partition_id(&self, number: u32) -> DeviceId {
+        let major = SCSI_DISK_ALLOCATOR.get().unwrap().major.get();
+        if self.id.major() == major && number < SCSI_DISK_MINORS {
+            DeviceId::new(major, MinorId::new(self.id.minor().get() + number))
+        } else {
+            EXTENDED_DEVICE_ID_ALLOCATOR.get().unwrap().allocate()
+        }
+    }
+}
+
+impl Drop for ScsiDiskSlot {
+    fn drop(&mut self) {
+        // This does nothing if the ID is not an extended device ID.
+        EXTENDED_DEVICE_ID_ALLOCATOR.get().unwrap().release(self.id);
+        SCSI_DISK_ALLOCATOR
+            .get()
+            .unwrap()
+            .index_allocator
+            .lock()
+            .free(self.index as usize);
+    }
+}
+
 pub(super) fn init() {
     EXTENDED_DEVICE_ID_ALLOCATOR.call_once(ExtendedDeviceIdAllocator::new);
+    SCSI_DISK_ALLOCATOR.call_once(ScsiDiskAllocator::new);
 }
@@ -4,12 +4,15 @@ use ostd::mm::{VmIo, VmReader, VmWriter};
 
 use super::{
     BLOCK_SIZE, BlockDevice,
-    bio::{Bio, BioEnqueueError, BioSegment, BioStatus, BioType, BioWaiter, SubmittedBio},
+    bio::{
+        Bio, BioEnqueueError, BioFlags, BioSegment, BioStatus, BioType, BioWaiter, SubmittedBio,
+    },
     id::{Bid, Sid},
 };
 use crate::{
     bio::{BioDirection, is_sector_aligned},
     prelude::*,
+    request_queue::BioPlug,
 };
 
 /// Implements several commonly used APIs for the block device to conveniently
@@ -78,17 +81,54 @@ impl dyn BlockDevice {
         bio.submit(self)
     }
 
-    /// Issues a sync request
+    /// Issues a sync request, which flushes the volatile write cache of the device.
     pub fn sync(&self) -> Result<BioStatus, BioEnqueueError> {
         let bio = Bio::new(
             BioType::Flush,
             Sid::from(Bid::from_offset(0)),
             vec![],
             Some(general_complete_fn),
-        );
+        )
+        .with_flags(BioFlags::SYNC);
         let status = bio.submit_and_wait(self)?;
         Ok(status)
     }
+
+    /// Discards the sectors in the `sid_range`, so that the device can reclaim the storage.
+    ///
+    /// The range is split into multiple bios if it exceeds the limit of the device. The data in
+    /// the discarded sectors are undefined afterwards. If the device does not support
+    /// discarding, [`BioStatus::NotSupported`] is returned.
+    pub fn discard(&self, sid_range: Range<Sid>) -> Result<BioStatus, BioEnqueueError> {
+        let max_nr_sectors = self.metadata().max_discard_sectors as u64;
+        if max_nr_sectors == 0 {
+            return Ok(BioStatus::NotSupported);
+        }
+
+        let mut start = sid_range.start;
+        while start < sid_range.end {
+            let nr_sectors = (sid_range.end.to_raw() - start.to_raw()).min(max_nr_sectors);
+            let end = start + nr_sectors;
+            let bio = Bio::new_discard(start..end, Some(general_complete_fn));
+            let status = bio.submit_and_wait(self)?;
+            if status != BioStatus::Complete {
+                return Ok(status);
+            }
+            start = end;
+        }
+
+        Ok(BioStatus::Complete)
+    }
+
+    /// Plugs the request queue of the block device until the returned [`BioPlug`] is dropped.
+    ///
+    /// The bios submitted in the meantime are more likely to be merged.
+    pub fn plug(&self) -> BioPlug<'_> {
+        match self.request_queue() {
+            Some(queue) => queue.plug(),
+            None => BioPlug::empty(),
+        }
+    }
 }
 
 impl VmIo for dyn BlockDevice {
@@ -176,38 +216,65 @@ impl VmIo for dyn BlockDevice {
 impl dyn BlockDevice {
     /// Asynchronously writes consecutive bytes of several sectors in size.
     pub fn write_bytes_async(&self, offset: usize, buf: &[u8]) -> ostd::Result<BioWaiter> {
-        let write_len = buf.len();
-        if !is_sector_aligned(offset) || !is_sector_aligned(write_len) {
-            return Err(ostd::Error::InvalidArgs);
-        }
-        if write_len == 0 {
+        let Some(bio) = new_write_bio(offset, buf)? else {
             return Ok(BioWaiter::new());
-        }
-
-        let bio = {
-            let num_blocks = {
-                let first = Bid::from_offset(offset).to_raw();
-                let last = Bid::from_offset(offset + write_len - 1).to_raw();
-                (last - first + 1) as usize
-            };
-            let bio_segment = BioSegment::alloc_inner(
-                num_blocks,
-                offset % BLOCK_SIZE,
-                write_len,
-                BioDirection::ToDevice,
-            );
-            bio_segment.write(0, &mut VmReader::from(buf).to_fallible())?;
-            Bio::new(
-                BioType::Write,
-                Sid::from_offset(offset),
-                vec![bio_segment],
-                Some(general_complete_fn),
-            )
         };
 
         let complete = bio.submit(self)?;
         Ok(complete)
     }
+
+    /// Synchronously writes consecutive bytes of several sectors in size with the flags.
+    ///
+    /// The flags can be used to order the write with respect to the cache flushes, e.g., for
+    /// the commit records of a journal.
+    pub fn write_bytes_with_flags(
+        &self,
+        offset: usize,
+        buf: &[u8],
+        flags: BioFlags,
+    ) -> ostd::Result<()> {
+        let Some(bio) = new_write_bio(offset, buf)? else {
+            return Ok(());
+        };
+
+        let status = bio.with_flags(flags).submit_and_wait(self)?;
+        match status {
+            BioStatus::Complete => Ok(()),
+            _ => Err(ostd::Error::IoError),
+        }
+    }
+}
+
+/// Creates a `Bio` that writes the bytes, or returns `None` if there are no bytes.
+fn new_write_bio(offset: usize, buf: &[u8]) -> ostd::Result<Option<Bio>> {
+    let write_len = buf.len();
+    if !is_sector_aligned(offset) || !is_sector_aligned(write_len) {
+        return Err(ostd::Error::InvalidArgs);
+    }
+    if write_len == 0 {
+        return Ok(None);
+    }
+
+    let num_blocks = {
+        let first = Bid::from_offset(offset).to_raw();
+        let last = Bid::from_offset(offset + write_len - 1).to_raw();
+        (last - first + 1) as usize
+    };
+    let bio_segment = BioSegment::alloc_inner(
+        num_blocks,
+        offset % BLOCK_SIZE,
+        write_len,
+        BioDirection::ToDevice,
+    );
+    bio_segment.write(0, &mut VmReader::from(buf).to_fallible())?;
+
+    Ok(Some(Bio::new(
+        BioType::Write,
+        Sid::from_offset(offset),
+        vec![bio_segment],
+        Some(general_complete_fn),
+    )))
 }
 
 fn general_complete_fn(bio: &SubmittedBio) {
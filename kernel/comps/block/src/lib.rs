@@ -44,13 +44,17 @@ pub mod request_queue;
 
 use ::device_id::DeviceId;
 use component::{ComponentInitError, init_component};
-pub use device_id::{EXTENDED_DEVICE_ID_ALLOCATOR, MajorIdOwner, acquire_major, allocate_major};
+pub use device_id::{
+    EXTENDED_DEVICE_ID_ALLOCATOR, MajorIdOwner, SCSI_DISK_ALLOCATOR, ScsiDiskSlot, acquire_major,
+    allocate_major,
+};
 use ostd::sync::Mutex;
-pub use partition::{PartitionInfo, PartitionNode};
+pub use partition::{PartitionInfo, PartitionNode, disk_name, parse_partitions, partition_name};
 
 use self::{
     bio::{BioEnqueueError, SubmittedBio},
     prelude::*,
+    request_queue::BioRequestQueue,
 };
 
 pub const BLOCK_SIZE: usize = ostd::mm::PAGE_SIZE;
@@ -74,13 +78,31 @@ pub trait BlockDevice: Send + Sync + Any + Debug {
         false
     }
 
+    /// Returns whether the partition table of the block device can be scanned.
+    ///
+    /// Partitions cannot have partitions. Some disks (e.g., unconfigured loop devices) cannot
+    /// have partitions either.
+    fn can_scan_partitions(&self) -> bool {
+        !self.is_partition()
+    }
+
     /// Sets the partitions of the block device.
+    ///
+    /// The old partitions, if any, are replaced by the new partitions in the registry.
     fn set_partitions(&self, _infos: Vec<Option<PartitionInfo>>) {}
 
     /// Returns the partitions of the block device.
     fn partitions(&self) -> Option<Vec<Arc<dyn BlockDevice>>> {
         None
     }
+
+    /// Returns the request queue of the block device.
+    ///
+    /// Block devices that handle bios without queueing them (e.g., loop devices) do not have
+    /// request queues.
+    fn request_queue(&self) -> Option<&BioRequestQueue> {
+        None
+    }
 }
 
 /// Metadata for a block device.
@@ -90,6 +112,14 @@ pub struct BlockDeviceMeta {
     pub max_nr_segments_per_bio: usize,
     /// The total number of sectors of the block device.
     pub nr_sectors: usize,
+    /// Whether the device supports the bios with [`bio::BioFlags::FUA`] natively.
+    ///
+    /// Otherwise, the flag is emulated by flushing the volatile write cache after the I/O.
+    pub supports_fua: bool,
+    /// The upper limit for the number of sectors per discard bio.
+    ///
+    /// Zero means that the device does not support discarding.
+    pub max_discard_sectors: usize,
     // Additional useful metadata can be added here in the future.
 }
 
@@ -152,17 +182,3 @@ fn init() -> Result<(), ComponentInitError> {
 
     Ok(())
 }
-
-#[init_component(process)]
-fn init_in_first_process() -> Result<(), component::ComponentInitError> {
-    let devices = collect_all();
-    for device in devices {
-        let Some(partition_info) = partition::parse(&device) else {
-            continue;
-        };
-
-        device.set_partitions(partition_info);
-    }
-
-    Ok(())
-}
@@ -8,8 +8,23 @@ use crate::{
     BlockDevice, BlockDeviceMeta, SECTOR_SIZE,
     bio::{BioEnqueueError, SubmittedBio},
     prelude::*,
+    request_queue::BioRequestQueue,
 };
 
+/// The maximum number of partitions of a disk.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/linux/blkdev.h>
+const MAX_NR_PARTITIONS: usize = 256;
+
+/// The maximum number of logical partitions in an extended partition.
+const MAX_NR_LOGICAL_PARTITIONS: usize = 128;
+
+/// The maximum size in bytes of the GPT partition entries.
+///
+/// The partition entries usually take 16 KiB. A larger size is allowed but bounded, since the
+/// partition table may be untrusted.
+const MAX_GPT_ENTRIES_SIZE: usize = 1024 * 1024;
+
 /// Represents a partition entry.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum PartitionInfo {
@@ -31,6 +46,18 @@ impl PartitionInfo {
             PartitionInfo::Gpt(entry) => entry.end_lba - entry.start_lba + 1,
         }
     }
+
+    /// Truncates the partition so that it has at most `total_sectors` sectors.
+    fn truncate(&mut self, total_sectors: u64) {
+        if total_sectors >= self.total_sectors() {
+            return;
+        }
+
+        match self {
+            PartitionInfo::Mbr(entry) => entry.total_sectors = total_sectors as u32,
+            PartitionInfo::Gpt(entry) => entry.end_lba = entry.start_lba + total_sectors - 1,
+        }
+    }
 }
 
 /// A MBR (Master Boot Record) partition table header.
@@ -118,9 +145,28 @@ struct GptHeader {
 }
 
 impl GptHeader {
+    /// The offset of the `crc32` field.
+    const CRC32_OFFSET: usize = 16;
+    /// The size of the header, excluding the padding.
+    const MIN_SIZE: usize = 92;
+
     fn check_signature(&self) -> bool {
         &self.signature.to_le_bytes() == b"EFI PART"
     }
+
+    fn check_crc32(&self) -> bool {
+        let size = self.size as usize;
+        if !(Self::MIN_SIZE..=size_of::<Self>()).contains(&size) {
+            return false;
+        }
+
+        // The checksum is calculated with the `crc32` field set to zero.
+        let mut crc = Crc32::new();
+        crc.update(&self.as_bytes()[..Self::CRC32_OFFSET]);
+        crc.update(&[0; 4]);
+        crc.update(&self.as_bytes()[Self::CRC32_OFFSET + 4..size]);
+        crc.finish() == self.crc32
+    }
 }
 
 /// A GPT (GUID Partition Table) partition entry.
@@ -142,24 +188,106 @@ pub struct GptEntry {
 }
 
 impl GptEntry {
-    fn is_valid(&self) -> bool {
-        self.type_guid != [0; 16]
+    fn is_valid(&self, last_lba: u64) -> bool {
+        self.type_guid != [0; 16] && self.start_lba <= self.end_lba && self.end_lba <= last_lba
+    }
+}
+
+/// The CRC32 checksum (the IEEE 802.3 variant), which is used by GPT.
+struct Crc32(u32);
+
+impl Crc32 {
+    const POLYNOMIAL: u32 = 0xEDB8_8320;
+
+    fn new() -> Self {
+        Self(!0)
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        for byte in data {
+            self.0 ^= *byte as u32;
+            for _ in 0..8 {
+                self.0 = if self.0 & 1 != 0 {
+                    (self.0 >> 1) ^ Self::POLYNOMIAL
+                } else {
+                    self.0 >> 1
+                };
+            }
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        !self.0
     }
 }
 
-pub(super) fn parse(device: &Arc<dyn BlockDevice>) -> Option<Vec<Option<PartitionInfo>>> {
-    let mbr = device.read_val::<MbrHeader>(0).unwrap();
+/// Parses the partition table of the block device.
+///
+/// Returns `None` if the block device has no valid partitions. The partition table may be
+/// untrusted (e.g., the image file of a loop device), so any malformed entry or I/O error stops
+/// the parsing instead of triggering a panic.
+///
+/// Like Linux, partitions beyond the end of the device are ignored, and partitions that extend
+/// beyond the end of the device are truncated.
+pub fn parse_partitions(device: &Arc<dyn BlockDevice>) -> Option<Vec<Option<PartitionInfo>>> {
+    let mbr = device.read_val::<MbrHeader>(0).ok()?;
 
     // 0xEE indicates a GPT Protective MBR, a fake partition covering the entire disk.
-    let partitions = if mbr.check_signature() && mbr.entries[0].type_ != 0xEE {
+    let mut partitions = if mbr.check_signature() && mbr.entries[0].type_ != 0xEE {
         parse_mbr(device, &mbr)
     } else {
         parse_gpt(device)
     };
+    partitions.truncate(MAX_NR_PARTITIONS);
+
+    let nr_sectors = device.metadata().nr_sectors as u64;
+    for partition in partitions.iter_mut() {
+        let Some(info) = partition else {
+            continue;
+        };
+
+        if info.start_sector() >= nr_sectors {
+            *partition = None;
+            continue;
+        }
+        info.truncate(nr_sectors - info.start_sector());
+    }
 
     partitions.iter().any(|p| p.is_some()).then_some(partitions)
 }
 
+/// Returns the name of the disk of `index` with the prefix.
+///
+/// With the prefix `vd`, the name starts at `vda`. The 26th disk is `vdz` and the 27th is `vdaa`.
+/// The last one for two lettered suffix is `vdzz` which is followed by `vdaaa`.
+pub fn disk_name(prefix: &str, mut index: u32) -> String {
+    let mut suffix = Vec::new();
+    loop {
+        suffix.push((b'a' + (index % 26) as u8) as char);
+        index /= 26;
+        if index == 0 {
+            break;
+        }
+        index -= 1;
+    }
+    suffix.reverse();
+    let mut name = String::from(prefix);
+    name.extend(suffix);
+    name
+}
+
+/// Returns the name of a partition of the disk.
+///
+/// Like Linux, a `p` is inserted between the disk name and the partition number if the disk
+/// name ends with a digit (e.g., `loop0p1` instead of `vda1`).
+pub fn partition_name(disk_name: &str, number: u32) -> String {
+    if disk_name.ends_with(|c: char| c.is_ascii_digit()) {
+        format!("{}p{}", disk_name, number)
+    } else {
+        format!("{}{}", disk_name, number)
+    }
+}
+
 fn parse_mbr(device: &Arc<dyn BlockDevice>, mbr: &MbrHeader) -> Vec<Option<PartitionInfo>> {
     let mut partitions = Vec::new();
     let mut extended_partition = None;
@@ -176,7 +304,7 @@ fn parse_mbr(device: &Arc<dyn BlockDevice>, mbr: &MbrHeader) -> Vec<Option<Parti
     }
 
     if let Some(start_sector) = extended_partition {
-        parse_ebr(device, &mut partitions, start_sector, 0);
+        parse_ebr(device, &mut partitions, start_sector, 0, 0);
     }
 
     partitions
@@ -187,13 +315,19 @@ fn parse_ebr(
     partitions: &mut Vec<Option<PartitionInfo>>,
     start_sector: u32,
     offset: u32,
+    depth: usize,
 ) {
-    let ebr_sector = start_sector + offset;
-    let mut ebr = device
-        .read_val::<MbrHeader>(ebr_sector as usize * SECTOR_SIZE)
-        .unwrap();
+    // A malformed EBR chain may contain a loop.
+    if depth >= MAX_NR_LOGICAL_PARTITIONS {
+        return;
+    }
+
+    let ebr_sector = start_sector.wrapping_add(offset);
+    let Ok(mut ebr) = device.read_val::<MbrHeader>(ebr_sector as usize * SECTOR_SIZE) else {
+        return;
+    };
     if ebr.entries[0].is_valid() {
-        ebr.entries[0].start_sector += ebr_sector;
+        ebr.entries[0].start_sector = ebr.entries[0].start_sector.wrapping_add(ebr_sector);
         partitions.push(Some(PartitionInfo::Mbr(ebr.entries[0])));
     }
 
@@ -203,49 +337,78 @@ fn parse_ebr(
             partitions,
             start_sector,
             ebr.entries[1].start_sector,
+            depth + 1,
         );
     }
 }
 
 fn parse_gpt(device: &Arc<dyn BlockDevice>) -> Vec<Option<PartitionInfo>> {
-    let mut partitions = Vec::new();
+    let Some(last_lba) = (device.metadata().nr_sectors as u64).checked_sub(1) else {
+        return Vec::new();
+    };
 
-    // The primary GPT Header must be located in LBA 1.
-    let gpt = device.read_val::<GptHeader>(SECTOR_SIZE).unwrap();
+    // The primary GPT header must be located in LBA 1. If it is corrupted, the backup GPT header
+    // in the last LBA is used.
+    let Some((gpt, entries)) = read_gpt(device, 1).or_else(|| read_gpt(device, last_lba)) else {
+        return Vec::new();
+    };
 
-    if !gpt.check_signature() {
-        return partitions;
-    }
+    let entry_size = gpt.size_of_partition_entry as usize;
+    entries
+        .chunks_exact(entry_size)
+        .take(MAX_NR_PARTITIONS)
+        .map(|bytes| {
+            let entry = GptEntry::from_first_bytes(bytes);
+            entry
+                .is_valid(last_lba)
+                .then_some(PartitionInfo::Gpt(entry))
+        })
+        .collect()
+}
 
-    // TODO: Check the CRC32 of the header and the partition entries, check the backup GPT header.
+/// Reads the GPT header in `lba` and the partition entries.
+///
+/// Returns `None` if the GPT header or the partition entries are invalid.
+fn read_gpt(device: &Arc<dyn BlockDevice>, lba: u64) -> Option<(GptHeader, Vec<u8>)> {
+    let gpt = device
+        .read_val::<GptHeader>(usize::try_from(lba).ok()?.checked_mul(SECTOR_SIZE)?)
+        .ok()?;
+    if !gpt.check_signature() || gpt.current_lba != lba || !gpt.check_crc32() {
+        return None;
+    }
 
     let entry_size = gpt.size_of_partition_entry as usize;
-    let entries_per_sector = SECTOR_SIZE / entry_size;
-    let total_sectors = gpt.nr_partition_entries as usize / entries_per_sector;
-    for i in 0..total_sectors {
-        let mut buf = [0u8; SECTOR_SIZE];
-        let offset = (gpt.partition_entry_lba as usize + i) * SECTOR_SIZE;
-        device.read_bytes(offset, buf.as_mut_slice()).unwrap();
-
-        for j in 0..entries_per_sector {
-            let entry_offset = j * gpt.size_of_partition_entry as usize;
-            let entry = GptEntry::from_first_bytes(&buf[entry_offset..entry_offset + entry_size]);
-            if entry.is_valid() {
-                partitions.push(Some(PartitionInfo::Gpt(entry)));
-            } else {
-                partitions.push(None);
-            }
-        }
+    if entry_size < size_of::<GptEntry>() || SECTOR_SIZE % entry_size != 0 {
+        return None;
+    }
+    let entries_size = (gpt.nr_partition_entries as usize).checked_mul(entry_size)?;
+    if entries_size > MAX_GPT_ENTRIES_SIZE {
+        return None;
     }
 
-    partitions
+    let mut entries = vec![0u8; entries_size.next_multiple_of(SECTOR_SIZE)];
+    let offset = usize::try_from(gpt.partition_entry_lba)
+        .ok()?
+        .checked_mul(SECTOR_SIZE)?;
+    device.read_bytes(offset, &mut entries).ok()?;
+    entries.truncate(entries_size);
+
+    let mut crc = Crc32::new();
+    crc.update(&entries);
+    if crc.finish() != gpt.crc32_of_partition_entries {
+        return None;
+    }
+
+    Some((gpt, entries))
 }
 
+/// A partition of a disk, which is a block device itself.
 #[derive(Debug)]
 pub struct PartitionNode {
     id: DeviceId,
     name: String,
     device: Arc<dyn BlockDevice>,
+    number: u32,
     info: PartitionInfo,
 }
 
@@ -268,6 +431,14 @@ impl BlockDevice for PartitionNode {
     fn id(&self) -> DeviceId {
         self.id
     }
+
+    fn is_partition(&self) -> bool {
+        true
+    }
+
+    fn request_queue(&self) -> Option<&BioRequestQueue> {
+        self.device.request_queue()
+    }
 }
 
 impl PartitionNode {
@@ -275,13 +446,30 @@ impl PartitionNode {
         id: DeviceId,
         name: String,
         device: Arc<dyn BlockDevice>,
+        number: u32,
         info: PartitionInfo,
     ) -> Self {
         Self {
             id,
             name,
             device,
+            number,
             info,
         }
     }
+
+    /// Returns the partition number, which starts from one.
+    pub fn number(&self) -> u32 {
+        self.number
+    }
+
+    /// Returns the disk that the partition belongs to.
+    pub fn disk(&self) -> &Arc<dyn BlockDevice> {
+        &self.device
+    }
+
+    /// Returns the partition entry.
+    pub fn info(&self) -> &PartitionInfo {
+        &self.info
+    }
 }
@@ -0,0 +1,485 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The multi-queue block I/O request queue.
+//!
+//! The producers (e.g., filesystems) submit bios to the queue, and the consumer (e.g., block
+//! device driver) continuously dispatches and processes requests from the queue. A bio goes
+//! through two stages before being dispatched:
+//!
+//! 1. The bio is staged in the software queue of the CPU that submits it. The staging queues are
+//!    per-CPU, so the submissions from different CPUs do not contend for the same lock. The bio is
+//!    merged into one of the recently staged requests if the sector ranges are contiguous.
+//! 2. When the consumer asks for a request, all the staged requests are inserted into the I/O
+//!    scheduler, which may merge them further and decides the order in which they are dispatched.
+//!
+//! The queue can also be plugged (see [`BioRequestQueue::plug`]) while a batch of bios is being
+//! submitted. The consumer is not woken up by the bios submitted when the queue is plugged, so
+//! that they have a better chance to be merged before being dispatched. However, the bios with
+//! [`BioFlags::SYNC`] always wake up the consumer, since their submitters are waiting for them.
+
+mod mq_deadline;
+mod scheduler;
+
+use alloc::boxed::Box;
+use core::sync::atomic::AtomicBool;
+
+use ostd::{
+    cpu::{CpuId, num_cpus},
+    sync::{Mutex, SpinLock, WaitQueue},
+};
+
+use self::scheduler::{IoScheduler, SCHEDULER_TYPES};
+use super::{
+    Error,
+    bio::{BioEnqueueError, BioFlags, BioType, SubmittedBio},
+    id::Sid,
+};
+use crate::prelude::*;
+
+/// The number of the most recently staged requests that a new bio tries to merge into.
+const MAX_MERGE_CANDIDATES: usize = 8;
+
+/// The number of staged requests that wakes up the consumer even if the queue is plugged.
+const MAX_PLUGGED_REQUESTS: usize = 32;
+
+/// The name of the default I/O scheduler.
+const DEFAULT_SCHEDULER: &str = "mq-deadline";
+
+/// A multi-queue block I/O request queue.
+///
+/// See the [module-level documentation](self) for details.
+pub struct BioRequestQueue {
+    /// The per-CPU software staging queues.
+    staging_queues: Box<[SpinLock<VecDeque<BioRequest>>]>,
+    scheduler: Mutex<Box<dyn IoScheduler>>,
+    /// The number of requests in the staging queues and the scheduler.
+    num_requests: AtomicUsize,
+    /// The number of requests in the staging queues.
+    num_staged_requests: AtomicUsize,
+    num_plugs: AtomicUsize,
+    /// Whether the queue is closed, e.g., after the device is removed.
+    is_closed: AtomicBool,
+    wait_queue: WaitQueue,
+    max_nr_segments_per_bio: usize,
+}
+
+impl BioRequestQueue {
+    /// Creates an empty queue.
+    pub fn new() -> Self {
+        Self::with_max_nr_segments_per_bio(usize::MAX)
+    }
+
+    /// Creates an empty queue with the upper bound for the number of segments in a bio.
+    ///
+    /// The upper bound also applies to the merged requests.
+    pub fn with_max_nr_segments_per_bio(max_nr_segments_per_bio: usize) -> Self {
+        let staging_queues = (0..num_cpus())
+            .map(|_| SpinLock::new(VecDeque::new()))
+            .collect();
+        let scheduler_type = SCHEDULER_TYPES
+            .iter()
+            .find(|type_| type_.name == DEFAULT_SCHEDULER)
+            .unwrap();
+
+        Self {
+            staging_queues,
+            scheduler: Mutex::new((scheduler_type.new)(max_nr_segments_per_bio)),
+            num_requests: AtomicUsize::new(0),
+            num_staged_requests: AtomicUsize::new(0),
+            num_plugs: AtomicUsize::new(0),
+            is_closed: AtomicBool::new(false),
+            wait_queue: WaitQueue::new(),
+            max_nr_segments_per_bio,
+        }
+    }
+
+    /// Returns the upper limit for the number of segments per bio.
+    pub fn max_nr_segments_per_bio(&self) -> usize {
+        self.max_nr_segments_per_bio
+    }
+
+    /// Returns the number of requests currently in this queue.
+    pub fn num_requests(&self) -> usize {
+        self.num_requests.load(Ordering::Relaxed)
+    }
+
+    /// Returns the name of the current I/O scheduler.
+    pub fn scheduler_name(&self) -> &'static str {
+        self.scheduler.lock().name()
+    }
+
+    /// Returns the names of all the available I/O schedulers.
+    pub fn available_schedulers() -> impl Iterator<Item = &'static str> {
+        SCHEDULER_TYPES.iter().map(|type_| type_.name)
+    }
+
+    /// Switches to the I/O scheduler of the given name.
+    ///
+    /// The requests in the old scheduler are moved to the new scheduler.
+    pub fn set_scheduler(&self, name: &str) -> Result<(), Error> {
+        let Some(scheduler_type) = SCHEDULER_TYPES.iter().find(|type_| type_.name == name) else {
+            return Err(Error::InvalidArgs);
+        };
+
+        let mut scheduler = self.scheduler.lock();
+        if scheduler.name() == name {
+            return Ok(());
+        }
+
+        let mut old_scheduler = core::mem::replace(
+            &mut *scheduler,
+            (scheduler_type.new)(self.max_nr_segments_per_bio),
+        );
+        let old_len = old_scheduler.len();
+        while let Some(request) = old_scheduler.dispatch() {
+            scheduler.insert(request);
+        }
+        self.num_requests.fetch_sub(old_len - scheduler.len(), Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Enqueues a `SubmittedBio` to this queue.
+    ///
+    /// The `SubmittedBio` is staged in the queue of the current CPU. It is merged into one of the
+    /// most recently staged requests if the type is same and the sector range is contiguous.
+    /// Otherwise, a new request is created for the `SubmittedBio`.
+    ///
+    /// This method will wake up the waiter if a new `BioRequest` is enqueued and the queue is not
+    /// plugged.
+    pub fn enqueue(&self, bio: SubmittedBio) -> Result<(), BioEnqueueError> {
+        if bio.segments().len() >= self.max_nr_segments_per_bio {
+            return Err(BioEnqueueError::TooBig);
+        }
+
+        // The CPU ID may be outdated, but it does not matter since it only selects a queue.
+        let cpu = u32::from(CpuId::current_racy()) as usize;
+        let mut staging_queue = self.staging_queues[cpu].lock();
+        // The flag must be checked with the lock held. See `Self::close`.
+        if self.is_closed.load(Ordering::Relaxed) {
+            return Err(BioEnqueueError::Refused);
+        }
+        if let Some(request) = staging_queue
+            .iter_mut()
+            .rev()
+            .take(MAX_MERGE_CANDIDATES)
+            .find(|request| {
+                request.can_merge(&bio)
+                    && request.num_segments() + bio.segments().len()
+                        <= self.max_nr_segments_per_bio
+            })
+        {
+            request.merge_bio(bio);
+            return Ok(());
+        }
+
+        let is_sync = bio.flags().contains(BioFlags::SYNC);
+        staging_queue.push_back(BioRequest::from(bio));
+        // The counters must be updated with the lock held. Otherwise, the request may be
+        // dispatched before the counters are increased.
+        self.num_requests.fetch_add(1, Ordering::Relaxed);
+        let num_staged_requests = self.num_staged_requests.fetch_add(1, Ordering::Relaxed) + 1;
+        drop(staging_queue);
+
+        if !self.is_plugged() || num_staged_requests >= MAX_PLUGGED_REQUESTS || is_sync {
+            self.wait_queue.wake_all();
+        }
+        Ok(())
+    }
+
+    /// Dequeues a `BioRequest` from this queue.
+    ///
+    /// This method will wait until one request can be retrieved.
+    pub fn dequeue(&self) -> BioRequest {
+        loop {
+            if let Some(request) = self.try_dequeue() {
+                return request;
+            }
+
+            self.wait_queue.wait_until(|| (self.num_requests() > 0).then_some(()));
+        }
+    }
+
+    /// Dequeues a `BioRequest` from this queue, or returns `None` if the queue is closed and
+    /// empty.
+    ///
+    /// This method will wait until one request can be retrieved or the queue is closed.
+    pub fn dequeue_unless_closed(&self) -> Option<BioRequest> {
+        loop {
+            if let Some(request) = self.try_dequeue() {
+                return Some(request);
+            }
+            if self.is_closed.load(Ordering::Relaxed) && self.num_requests() == 0 {
+                return None;
+            }
+
+            self.wait_queue.wait_until(|| {
+                (self.num_requests() > 0 || self.is_closed.load(Ordering::Relaxed)).then_some(())
+            });
+        }
+    }
+
+    /// Closes this queue, so that no more bios can be enqueued.
+    ///
+    /// The requests that have been enqueued can still be dequeued. The consumer that waits in
+    /// [`Self::dequeue_unless_closed`] will be woken up.
+    pub fn close(&self) {
+        self.is_closed.store(true, Ordering::Relaxed);
+        // Wait for the ongoing enqueuing operations, which may not observe the flag.
+        for staging_queue in self.staging_queues.iter() {
+            drop(staging_queue.lock());
+        }
+
+        self.wait_queue.wake_all();
+    }
+
+    /// Tries to dequeue a `BioRequest` from this queue without waiting.
+    pub fn try_dequeue(&self) -> Option<BioRequest> {
+        if self.num_requests() == 0 {
+            return None;
+        }
+
+        let mut scheduler = self.scheduler.lock();
+        self.flush_staging_queues(scheduler.as_mut());
+
+        let request = scheduler.dispatch()?;
+        self.num_requests.fetch_sub(1, Ordering::Relaxed);
+        Some(request)
+    }
+
+    /// Plugs this queue until the returned [`BioPlug`] is dropped.
+    ///
+    /// This is useful when submitting a batch of bios that are likely to be merged.
+    pub fn plug(&self) -> BioPlug<'_> {
+        self.num_plugs.fetch_add(1, Ordering::Relaxed);
+        BioPlug { queue: Some(self) }
+    }
+
+    fn is_plugged(&self) -> bool {
+        self.num_plugs.load(Ordering::Relaxed) > 0
+    }
+
+    /// Moves all the staged requests to the scheduler.
+    fn flush_staging_queues(&self, scheduler: &mut dyn IoScheduler) {
+        let old_len = scheduler.len();
+        let mut num_flushed = 0;
+
+        for staging_queue in self.staging_queues.iter() {
+            let requests = {
+                let mut staging_queue = staging_queue.lock();
+                self.num_staged_requests.fetch_sub(staging_queue.len(), Ordering::Relaxed);
+                core::mem::take(&mut *staging_queue)
+            };
+
+            num_flushed += requests.len();
+            for request in requests {
+                scheduler.insert(request);
+            }
+        }
+
+        // Some requests may have been merged by the scheduler.
+        let num_merged = old_len + num_flushed - scheduler.len();
+        self.num_requests.fetch_sub(num_merged, Ordering::Relaxed);
+    }
+}
+
+impl Default for BioRequestQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for BioRequestQueue {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("BioRequestQueue")
+            .field("num_requests", &self.num_requests())
+            .field("scheduler", &self.scheduler.lock())
+            .finish()
+    }
+}
+
+/// A guard that keeps a [`BioRequestQueue`] plugged.
+///
+/// When the guard is dropped, the consumer of the queue is woken up to process the bios submitted
+/// when the queue is plugged.
+#[must_use]
+#[derive(Debug)]
+pub struct BioPlug<'a> {
+    queue: Option<&'a BioRequestQueue>,
+}
+
+impl BioPlug<'_> {
+    /// Creates a guard that plugs nothing.
+    ///
+    /// This is for the block devices that do not have request queues.
+    pub fn empty() -> Self {
+        Self { queue: None }
+    }
+}
+
+impl Drop for BioPlug<'_> {
+    fn drop(&mut self) {
+        let Some(queue) = self.queue else {
+            return;
+        };
+
+        queue.num_plugs.fetch_sub(1, Ordering::Relaxed);
+        if queue.num_requests() > 0 {
+            queue.wait_queue.wake_all();
+        }
+    }
+}
+
+/// A block I/O request dequeued from [`BioRequestQueue`].
+///
+/// This `BioRequest` type is more friendly to storage medium than `SubmittedBio` for two reasons.
+///
+/// First, a `BioRequest` can represent a merged request over multiple `SubmittedBio`s
+/// that (1) are of the same request type and flags and (2) are contiguous in terms of target
+/// sectors.
+/// This helps reduce the number of I/O requests submitted to the underlying storage medium.
+///
+/// Second, a `BioRequest` provides the physical sector addresses suitable for storage medium.
+/// The sector addresses returned from `SubmittedBio::sid_range()` are logical ones:
+/// they need to be adjusted with `SubmittedBio::sid_offset()` to calculate the physical ones.
+/// This calculation is handled internally by `BioRequest`.
+/// One can simply call `BioRequest::sid_range()` to obtain the physical sector addresses.
+#[derive(Debug)]
+pub struct BioRequest {
+    /// The type of the I/O
+    type_: BioType,
+    /// The flags of all the submitted bios
+    flags: BioFlags,
+    /// The physical range of target sectors on the device
+    sid_range: Range<Sid>,
+    /// The number of segments
+    num_segments: usize,
+    /// The submitted bios
+    bios: VecDeque<SubmittedBio>,
+}
+
+impl BioRequest {
+    /// Returns the type of the I/O.
+    pub fn type_(&self) -> BioType {
+        self.type_
+    }
+
+    /// Returns the flags, which are the same for all the `SubmittedBio`s.
+    pub fn flags(&self) -> BioFlags {
+        self.flags
+    }
+
+    /// Returns the range of sector id on device.
+    pub fn sid_range(&self) -> &Range<Sid> {
+        &self.sid_range
+    }
+
+    /// Returns an iterator to the `SubmittedBio`s.
+    pub fn bios(&self) -> impl Iterator<Item = &SubmittedBio> {
+        self.bios.iter()
+    }
+
+    /// Returns the number of sectors of this request.
+    pub fn num_sectors(&self) -> usize {
+        (self.sid_range.end.to_raw() - self.sid_range.start.to_raw())
+            .try_into()
+            .unwrap()
+    }
+
+    /// Returns the number of segments.
+    pub fn num_segments(&self) -> usize {
+        self.num_segments
+    }
+
+    /// Returns `true` if can merge the `SubmittedBio`, `false` otherwise.
+    pub fn can_merge(&self, rq_bio: &SubmittedBio) -> bool {
+        if rq_bio.type_() != self.type_ || rq_bio.flags() != self.flags {
+            return false;
+        }
+        // A merged discard request could exceed the limit of the device.
+        if self.type_ == BioType::Discard {
+            return false;
+        }
+
+        let sid_offset = rq_bio.sid_offset();
+
+        rq_bio.sid_range().start + sid_offset == self.sid_range.end
+            || rq_bio.sid_range().end + sid_offset == self.sid_range.start
+    }
+
+    /// Merges the `SubmittedBio` into this request.
+    ///
+    /// The merged `SubmittedBio` can only be placed at the front or back.
+    ///
+    /// # Panics
+    ///
+    /// If the `SubmittedBio` can not be merged, this method will panic.
+    pub fn merge_bio(&mut self, rq_bio: SubmittedBio) {
+        assert!(self.can_merge(&rq_bio));
+
+        let rq_bio_nr_segments = rq_bio.segments().len();
+        let sid_offset = rq_bio.sid_offset();
+
+        if rq_bio.sid_range().start + sid_offset == self.sid_range.end {
+            self.sid_range.end = rq_bio.sid_range().end + sid_offset;
+            self.bios.push_back(rq_bio);
+        } else {
+            self.sid_range.start = rq_bio.sid_range().start + sid_offset;
+            self.bios.push_front(rq_bio);
+        }
+
+        self.num_segments += rq_bio_nr_segments;
+    }
+
+    /// Returns `true` if can merge the other `BioRequest`, `false` otherwise.
+    pub fn can_merge_request(&self, other: &BioRequest) -> bool {
+        other.type_ == self.type_
+            && other.type_ != BioType::Discard
+            && other.flags == self.flags
+            && (other.sid_range.start == self.sid_range.end
+                || other.sid_range.end == self.sid_range.start)
+    }
+
+    /// Merges the other `BioRequest` into this request.
+    ///
+    /// The merged `BioRequest` can only be placed at the front or back.
+    ///
+    /// # Panics
+    ///
+    /// If the `BioRequest` can not be merged, this method will panic.
+    pub fn merge_request(&mut self, other: BioRequest) {
+        assert!(self.can_merge_request(&other));
+
+        if other.sid_range.start == self.sid_range.end {
+            self.sid_range.end = other.sid_range.end;
+            self.bios.extend(other.bios);
+        } else {
+            self.sid_range.start = other.sid_range.start;
+            for bio in other.bios.into_iter().rev() {
+                self.bios.push_front(bio);
+            }
+        }
+
+        self.num_segments += other.num_segments;
+    }
+}
+
+impl From<SubmittedBio> for BioRequest {
+    fn from(bio: SubmittedBio) -> Self {
+        let mut sid_range = bio.sid_range().clone();
+        sid_range.start = sid_range.start + bio.sid_offset();
+        sid_range.end = sid_range.end + bio.sid_offset();
+
+        Self {
+            type_: bio.type_(),
+            flags: bio.flags(),
+            sid_range,
+            num_segments: bio.segments().len(),
+            bios: {
+                let mut bios = VecDeque::with_capacity(1);
+                bios.push_front(bio);
+                bios
+            },
+        }
+    }
+}
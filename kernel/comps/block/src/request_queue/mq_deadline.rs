@@ -0,0 +1,251 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The mq-deadline I/O scheduler.
+//!
+//! The reads and the writes are kept separately. In each direction, the requests are both sorted
+//! by the sector addresses and ordered by the arrival times. The requests are dispatched in batches
+//! in the ascending order of the sector addresses to reduce seeking, and a new batch starts from
+//! the oldest request if it has expired. The reads are preferred over the writes, but the writes
+//! cannot be starved by more than [`WRITES_STARVED`] batches of reads.
+//!
+//! Reference: <https://elixir.bootlin.com/linux/v6.18/source/block/mq-deadline.c>
+
+use alloc::{boxed::Box, collections::BTreeSet};
+use core::time::Duration;
+
+use ostd::timer::Jiffies;
+
+use super::{
+    BioRequest,
+    scheduler::{IoScheduler, SchedulerType},
+};
+use crate::{bio::BioType, prelude::*};
+
+pub(super) const SCHEDULER_TYPE: SchedulerType = SchedulerType {
+    name: "mq-deadline",
+    new: MqDeadline::new,
+};
+
+/// The time before a read request expires.
+const READ_EXPIRE: Duration = Duration::from_millis(500);
+/// The time before a write request expires.
+const WRITE_EXPIRE: Duration = Duration::from_secs(5);
+/// The maximum number of requests in a batch.
+const FIFO_BATCH: usize = 16;
+/// The maximum number of read batches that can be dispatched while writes are pending.
+const WRITES_STARVED: usize = 2;
+
+#[derive(Debug)]
+struct MqDeadline {
+    /// The requests that are not sorted (e.g., flushes), which are dispatched first.
+    unsorted: VecDeque<BioRequest>,
+    reads: SortedQueue,
+    writes: SortedQueue,
+    /// The direction of the current batch.
+    batch_dir: Direction,
+    /// The number of requests dispatched in the current batch.
+    batching: usize,
+    /// The number of read batches dispatched while writes are pending.
+    starved: usize,
+    /// The sequence number of the next request, which reflects the arrival order.
+    next_seq: u64,
+    max_nr_segments: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Direction {
+    Read,
+    Write,
+}
+
+impl MqDeadline {
+    fn new(max_nr_segments: usize) -> Box<dyn IoScheduler> {
+        Box::new(Self {
+            unsorted: VecDeque::new(),
+            reads: SortedQueue::new(),
+            writes: SortedQueue::new(),
+            batch_dir: Direction::Read,
+            batching: 0,
+            starved: 0,
+            next_seq: 0,
+            max_nr_segments,
+        })
+    }
+
+    fn queue_mut(&mut self, dir: Direction) -> &mut SortedQueue {
+        match dir {
+            Direction::Read => &mut self.reads,
+            Direction::Write => &mut self.writes,
+        }
+    }
+
+    /// Chooses the direction of a new batch.
+    fn choose_direction(&mut self) -> Option<Direction> {
+        if self.reads.is_empty() {
+            return (!self.writes.is_empty()).then_some(Direction::Write);
+        }
+
+        if !self.writes.is_empty() {
+            if self.starved >= WRITES_STARVED {
+                return Some(Direction::Write);
+            }
+            self.starved += 1;
+        }
+
+        Some(Direction::Read)
+    }
+}
+
+impl IoScheduler for MqDeadline {
+    fn name(&self) -> &'static str {
+        SCHEDULER_TYPE.name
+    }
+
+    fn insert(&mut self, request: BioRequest) {
+        let (dir, expire) = match request.type_() {
+            BioType::Read => (Direction::Read, READ_EXPIRE),
+            BioType::Write => (Direction::Write, WRITE_EXPIRE),
+            BioType::Flush | BioType::Discard => {
+                self.unsorted.push_back(request);
+                return;
+            }
+        };
+
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        let deadline = Jiffies::elapsed().as_duration() + expire;
+        let max_nr_segments = self.max_nr_segments;
+        self.queue_mut(dir).insert(request, seq, deadline, max_nr_segments);
+    }
+
+    fn dispatch(&mut self) -> Option<BioRequest> {
+        if let Some(request) = self.unsorted.pop_front() {
+            return Some(request);
+        }
+
+        // Continues the current batch if possible.
+        if self.batching < FIFO_BATCH {
+            let batch_dir = self.batch_dir;
+            if let Some(seq) = self.queue_mut(batch_dir).next_in_sector_order() {
+                self.batching += 1;
+                return Some(self.queue_mut(batch_dir).remove(seq));
+            }
+        }
+
+        // Starts a new batch.
+        let dir = self.choose_direction()?;
+        if dir == Direction::Write {
+            self.starved = 0;
+        }
+        self.batch_dir = dir;
+        self.batching = 1;
+
+        let now = Jiffies::elapsed().as_duration();
+        let queue = self.queue_mut(dir);
+        let (oldest_seq, deadline) = queue.oldest().unwrap();
+        // Goes back to the oldest request if it has expired or if there are no more requests
+        // after the last dispatched one.
+        let seq = if deadline <= now {
+            oldest_seq
+        } else {
+            queue.next_in_sector_order().unwrap_or(oldest_seq)
+        };
+        Some(queue.remove(seq))
+    }
+
+    fn len(&self) -> usize {
+        self.unsorted.len() + self.reads.len() + self.writes.len()
+    }
+}
+
+/// The requests in one direction.
+#[derive(Debug)]
+struct SortedQueue {
+    /// The requests and their deadlines, indexed by the sequence numbers.
+    ///
+    /// Since the sequence numbers increase with the arrival times, the first entry is always the
+    /// oldest request.
+    requests: BTreeMap<u64, (BioRequest, Duration)>,
+    /// The start sectors and the sequence numbers of the requests.
+    sorted: BTreeSet<(u64, u64)>,
+    /// The end sector of the last dispatched request.
+    last_end: u64,
+}
+
+impl SortedQueue {
+    fn new() -> Self {
+        Self {
+            requests: BTreeMap::new(),
+            sorted: BTreeSet::new(),
+            last_end: 0,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    /// Inserts a request, merging it into an adjacent request if possible.
+    fn insert(
+        &mut self,
+        request: BioRequest,
+        seq: u64,
+        deadline: Duration,
+        max_nr_segments: usize,
+    ) {
+        let start = request.sid_range().start.to_raw();
+        let end = request.sid_range().end.to_raw();
+
+        // The merged request keeps the sequence number and the deadline of the existing one,
+        // which is older.
+        let prev = self.sorted.range(..(start, 0)).next_back().copied();
+        let next = self.sorted.range((end, 0)..).next().copied();
+        for (existing_start, existing_seq) in [prev, next].into_iter().flatten() {
+            let (existing, _) = self.requests.get_mut(&existing_seq).unwrap();
+            if !existing.can_merge_request(&request)
+                || existing.num_segments() + request.num_segments() > max_nr_segments
+            {
+                continue;
+            }
+
+            existing.merge_request(request);
+            let new_start = existing.sid_range().start.to_raw();
+            if new_start != existing_start {
+                self.sorted.remove(&(existing_start, existing_seq));
+                self.sorted.insert((new_start, existing_seq));
+            }
+            return;
+        }
+
+        self.requests.insert(seq, (request, deadline));
+        self.sorted.insert((start, seq));
+    }
+
+    /// Returns the sequence number of the next request in the ascending order of the sectors,
+    /// starting from the end of the last dispatched request.
+    fn next_in_sector_order(&self) -> Option<u64> {
+        self.sorted
+            .range((self.last_end, 0)..)
+            .next()
+            .map(|(_, seq)| *seq)
+    }
+
+    /// Returns the sequence number and the deadline of the oldest request.
+    fn oldest(&self) -> Option<(u64, Duration)> {
+        self.requests
+            .first_key_value()
+            .map(|(seq, (_, deadline))| (*seq, *deadline))
+    }
+
+    /// Removes the request of the sequence number for dispatching.
+    fn remove(&mut self, seq: u64) -> BioRequest {
+        let (request, _) = self.requests.remove(&seq).unwrap();
+        self.sorted.remove(&(request.sid_range().start.to_raw(), seq));
+        self.last_end = request.sid_range().end.to_raw();
+        request
+    }
+}
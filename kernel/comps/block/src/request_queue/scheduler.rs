@@ -0,0 +1,83 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::boxed::Box;
+
+use super::{BioRequest, mq_deadline};
+use crate::prelude::*;
+
+/// An I/O scheduler, which decides the order in which the requests are dispatched.
+pub(super) trait IoScheduler: Send + Sync + Debug {
+    /// Returns the name of the scheduler.
+    fn name(&self) -> &'static str;
+
+    /// Inserts a request, which may be merged into an existing request.
+    fn insert(&mut self, request: BioRequest);
+
+    /// Dispatches the next request, if any.
+    fn dispatch(&mut self) -> Option<BioRequest>;
+
+    /// Returns the number of requests in the scheduler.
+    fn len(&self) -> usize;
+}
+
+/// A type of I/O schedulers.
+pub(super) struct SchedulerType {
+    /// The name of the scheduler.
+    pub(super) name: &'static str,
+    /// Creates a scheduler with the upper bound for the number of segments in a request.
+    pub(super) new: fn(usize) -> Box<dyn IoScheduler>,
+}
+
+/// All the supported I/O schedulers.
+pub(super) const SCHEDULER_TYPES: &[SchedulerType] = &[
+    SchedulerType {
+        name: "none",
+        new: NoneScheduler::new,
+    },
+    mq_deadline::SCHEDULER_TYPE,
+];
+
+/// The `none` scheduler, which dispatches the requests in the FIFO order.
+///
+/// The new request is merged into the last request if possible. This is suitable for the devices
+/// that are fast at random accesses or reorder the requests by themselves.
+#[derive(Debug)]
+struct NoneScheduler {
+    requests: VecDeque<BioRequest>,
+    max_nr_segments: usize,
+}
+
+impl NoneScheduler {
+    fn new(max_nr_segments: usize) -> Box<dyn IoScheduler> {
+        Box::new(Self {
+            requests: VecDeque::new(),
+            max_nr_segments,
+        })
+    }
+}
+
+impl IoScheduler for NoneScheduler {
+    fn name(&self) -> &'static str {
+        "none"
+    }
+
+    fn insert(&mut self, request: BioRequest) {
+        if let Some(last) = self.requests.back_mut()
+            && last.can_merge_request(&request)
+            && last.num_segments() + request.num_segments() <= self.max_nr_segments
+        {
+            last.merge_request(request);
+            return;
+        }
+
+        self.requests.push_back(request);
+    }
+
+    fn dispatch(&mut self) -> Option<BioRequest> {
+        self.requests.pop_front()
+    }
+
+    fn len(&self) -> usize {
+        self.requests.len()
+    }
+}
@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use alloc::{sync::Arc, vec::Vec};
+use alloc::{string::ToString, sync::Arc, vec::Vec};
 
 use aster_console::{
     AnyConsoleDevice, ConsoleCallback, ConsoleSetFontError,
@@ -34,7 +34,11 @@ pub(crate) fn init() {
         return;
     };
 
-    FRAMEBUFFER_CONSOLE.call_once(|| Arc::new(FramebufferConsole::new(fb.clone())));
+    let console = FRAMEBUFFER_CONSOLE.call_once(|| Arc::new(FramebufferConsole::new(fb.clone())));
+
+    // Register the console as early as possible, so that the kernel messages can be seen on
+    // machines without serial ports.
+    aster_console::register_device(CONSOLE_NAME.to_string(), console.clone());
 }
 
 impl AnyConsoleDevice for FramebufferConsole {
@@ -0,0 +1,85 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The hardware random number generators of Asterinas.
+//!
+//! The drivers of hardware random number generators register their devices here. The kernel
+//! reads from the current device to serve `/dev/hwrng` and to feed entropy into its random
+//! number generator. Like Linux, the current device is the one with the best quality.
+
+#![no_std]
+#![deny(unsafe_code)]
+
+extern crate alloc;
+
+use alloc::{collections::BTreeMap, fmt::Debug, string::String, sync::Arc, vec::Vec};
+use core::any::Any;
+
+use component::{ComponentInitError, init_component};
+use ostd::sync::SpinLock;
+use spin::Once;
+
+/// The quality of a device whose output is fully random.
+///
+/// The quality is the number of bits of entropy per 1024 bits of output.
+pub const MAX_QUALITY: u16 = 1024;
+
+pub trait AnyHwRngDevice: Send + Sync + Any + Debug {
+    /// Reads random bytes into the buffer.
+    ///
+    /// This method may sleep until the device produces random bytes. It returns the number of
+    /// bytes read, which may be less than the length of the buffer.
+    fn read(&self, buf: &mut [u8]) -> usize;
+
+    /// Returns the number of bits of entropy per 1024 bits of output.
+    fn quality(&self) -> u16 {
+        MAX_QUALITY
+    }
+}
+
+pub fn register_device(name: String, device: Arc<dyn AnyHwRngDevice>) {
+    COMPONENT
+        .get()
+        .unwrap()
+        .hwrng_device_table
+        .lock()
+        .insert(name, device);
+}
+
+pub fn all_devices() -> Vec<(String, Arc<dyn AnyHwRngDevice>)> {
+    let hwrng_devices = COMPONENT.get().unwrap().hwrng_device_table.lock();
+    hwrng_devices
+        .iter()
+        .map(|(name, device)| (name.clone(), device.clone()))
+        .collect()
+}
+
+/// Returns the device with the best quality, if any.
+pub fn current_device() -> Option<(String, Arc<dyn AnyHwRngDevice>)> {
+    let hwrng_devices = COMPONENT.get().unwrap().hwrng_device_table.lock();
+    hwrng_devices
+        .iter()
+        .max_by_key(|(_, device)| device.quality())
+        .map(|(name, device)| (name.clone(), device.clone()))
+}
+
+static COMPONENT: Once<Component> = Once::new();
+
+#[init_component]
+fn component_init() -> Result<(), ComponentInitError> {
+    let component = Component::init()?;
+    COMPONENT.call_once(|| component);
+    Ok(())
+}
+
+#[derive(Debug)]
+struct Component {
+    hwrng_device_table: SpinLock<BTreeMap<String, Arc<dyn AnyHwRngDevice>>>,
+}
+
+impl Component {
+    pub fn init() -> Result<Self, ComponentInitError> {
+        Ok(Self {
+            hwrng_device_table: SpinLock::new(BTreeMap::new()),
+        })
+    }
+}
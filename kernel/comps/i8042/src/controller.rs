@@ -5,12 +5,14 @@
 //! Reference: <https://wiki.osdev.org/I8042_PS/2_Controller>
 //!
 
+use alloc::sync::Arc;
 use core::sync::atomic::{AtomicBool, Ordering};
 
 use bitflags::bitflags;
 use ostd::{
     arch::{device::io_port::ReadWriteAccess, kernel::ACPI_INFO},
     io::IoPort,
+    power::{DevicePmOps, register_device_pm_ops},
     sync::{LocalIrqDisabled, SpinLock},
 };
 use spin::Once;
@@ -116,10 +118,59 @@ pub(super) fn init() -> Result<(), I8042ControllerError> {
     controller.write_configuration(&config)?;
     // Flush the output buffer to ensure that new data can trigger interrupts.
     controller.flush_output_buffer();
+    drop(controller);
+
+    register_device_pm_ops(Arc::new(I8042PmOps {
+        config: SpinLock::new(None),
+    }));
 
     Ok(())
 }
 
+/// The power management operations of the i8042 controller.
+struct I8042PmOps {
+    /// The configuration saved when the controller is suspended.
+    config: SpinLock<Option<Configuration>>,
+}
+
+impl DevicePmOps for I8042PmOps {
+    fn name(&self) -> &str {
+        "i8042"
+    }
+
+    fn suspend(&self) -> ostd::Result<()> {
+        let mut controller = I8042_CONTROLLER.get().unwrap().lock();
+
+        let config = controller
+            .read_configuration()
+            .map_err(|_| ostd::Error::IoError)?;
+        let quiesced_config = config.difference(
+            Configuration::FIRST_PORT_INTERRUPT_ENABLED
+                | Configuration::SECOND_PORT_INTERRUPT_ENABLED,
+        );
+        controller
+            .write_configuration(&quiesced_config)
+            .map_err(|_| ostd::Error::IoError)?;
+
+        *self.config.lock() = Some(config);
+        Ok(())
+    }
+
+    fn resume(&self) {
+        let Some(config) = self.config.lock().take() else {
+            return;
+        };
+
+        // TODO: The keyboard and the mouse may have been reset during the sleep, so they should
+        // be initialized again.
+        let mut controller = I8042_CONTROLLER.get().unwrap().lock();
+        if let Err(err) = controller.write_configuration(&config) {
+            log::warn!("i8042 controller resume failed: {:?}", err);
+        }
+        controller.flush_output_buffer();
+    }
+}
+
 /// An I8042 PS/2 Controller.
 pub(super) struct I8042Controller {
     data_port: IoPort<u8, ReadWriteAccess>,
@@ -57,6 +57,13 @@ pub(super) fn init(controller: &mut I8042Controller) -> Result<(), I8042Controll
         mouse_type =
             MouseType::from_device_id(new_device_id).ok_or(I8042ControllerError::DeviceUnknown)?;
     }
+    if mouse_type == MouseType::IntelliMouse && init_ctx.enable_intellimouse_explorer().is_ok() {
+        // Query the device ID again.
+        let new_device_id = init_ctx.get_device_id()?;
+        log::info!("PS/2 mouse upgraded device ID: 0x{:02X}", new_device_id);
+        mouse_type =
+            MouseType::from_device_id(new_device_id).ok_or(I8042ControllerError::DeviceUnknown)?;
+    }
 
     // Enable data reporting.
     init_ctx.enable_data_reporting()?;
@@ -73,7 +80,7 @@ pub(super) fn init(controller: &mut I8042Controller) -> Result<(), I8042Controll
     IRQ_LINE.call_once(|| irq_line);
 
     // Create and register the i8042 mouse device.
-    let mouse_device = Arc::new(I8042Mouse::new());
+    let mouse_device = Arc::new(I8042Mouse::new(mouse_type));
     let registered_device = aster_input::register_device(mouse_device);
     REGISTERED_DEVICE.call_once(|| registered_device);
 
@@ -106,6 +113,18 @@ impl InitCtx<'_> {
         Ok(())
     }
 
+    fn enable_intellimouse_explorer(&mut self) -> Result<(), I8042ControllerError> {
+        const SAMPLE_RATE_200: u8 = 200;
+        const SAMPLE_RATE_80: u8 = 80;
+
+        // Set the sample rate to 200, then to 200 again, and finally to 80.
+        // Reference: <https://wiki.osdev.org/Mouse_Input#Init/Detection_Command_Sequences>
+        self.set_sample_rate(SAMPLE_RATE_200)?;
+        self.set_sample_rate(SAMPLE_RATE_200)?;
+        self.set_sample_rate(SAMPLE_RATE_80)?;
+        Ok(())
+    }
+
     fn set_sample_rate(&mut self, rate: u8) -> Result<(), I8042ControllerError> {
         let mut buf = [0u8; cmd::SetSampleRate::RES_LEN];
         self.command::<cmd::SetSampleRate>(&[rate], &mut buf)?;
@@ -149,7 +168,7 @@ struct I8042Mouse {
 }
 
 impl I8042Mouse {
-    fn new() -> Self {
+    fn new(mouse_type: MouseType) -> Self {
         let mut capability = InputCapability::new();
 
         // Mouse supports key events and relative movement events.
@@ -161,11 +180,17 @@ impl I8042Mouse {
         capability.set_supported_key(KeyCode::BtnLeft);
         capability.set_supported_key(KeyCode::BtnRight);
         capability.set_supported_key(KeyCode::BtnMiddle);
+        if mouse_type == MouseType::IntelliMouseExplorer {
+            capability.set_supported_key(KeyCode::BtnSide);
+            capability.set_supported_key(KeyCode::BtnExtra);
+        }
 
         // Add relative axes for movement.
         capability.set_supported_relative_axis(RelCode::X);
         capability.set_supported_relative_axis(RelCode::Y);
-        capability.set_supported_relative_axis(RelCode::Wheel);
+        if mouse_type != MouseType::Standard {
+            capability.set_supported_relative_axis(RelCode::Wheel);
+        }
 
         Self {
             // Standard name for i8042 PS/2 mouse devices.
@@ -236,6 +261,7 @@ fn handle_mouse_input(_trap_frame: &TrapFrame) {
 enum MouseType {
     Standard,
     IntelliMouse,
+    IntelliMouseExplorer,
 }
 
 impl MouseType {
@@ -251,9 +277,8 @@ impl MouseType {
 
         match device_id {
             DEVICE_ID_STANDARD_MOUSE => Some(MouseType::Standard),
-            DEVICE_ID_INTELLIMOUSE | DEVICE_ID_INTELLIMOUSE_EXPLORER => {
-                Some(MouseType::IntelliMouse)
-            }
+            DEVICE_ID_INTELLIMOUSE => Some(MouseType::IntelliMouse),
+            DEVICE_ID_INTELLIMOUSE_EXPLORER => Some(MouseType::IntelliMouseExplorer),
             _ => None,
         }
     }
@@ -261,7 +286,9 @@ impl MouseType {
     fn packet_len(&self) -> usize {
         match self {
             MouseType::Standard => Self::PACKET_LEN_STANDARD,
-            MouseType::IntelliMouse => Self::PACKET_LEN_INTELLIMOUSE,
+            MouseType::IntelliMouse | MouseType::IntelliMouseExplorer => {
+                Self::PACKET_LEN_INTELLIMOUSE
+            }
         }
     }
 }
@@ -274,6 +301,8 @@ struct PacketState {
     prev_left: bool,
     prev_right: bool,
     prev_middle: bool,
+    prev_side: bool,
+    prev_extra: bool,
 }
 
 impl PacketState {
@@ -285,6 +314,8 @@ impl PacketState {
             prev_left: false,
             prev_right: false,
             prev_middle: false,
+            prev_side: false,
+            prev_extra: false,
         }
     }
 
@@ -329,10 +360,13 @@ impl PacketState {
         const INDEX_X: usize = 1;
         const INDEX_Y: usize = 2;
         const INDEX_WHEEL: usize = 3;
+        const EXPLORER_WHEEL_MASK: u8 = 0x0F;
+        const EXPLORER_BUTTON_SIDE_MASK: u8 = 0x10;
+        const EXPLORER_BUTTON_EXTRA_MASK: u8 = 0x20;
 
-        // Currently, this method can generate at most 7 events. Don't forget to update this when
+        // Currently, this method can generate at most 9 events. Don't forget to update this when
         // modifying the logic below!
-        let mut events = Vec::with_capacity(7);
+        let mut events = Vec::with_capacity(9);
 
         let status = self.packet[INDEX_STATUS];
         if (status & OVERFLOWED_MASK) != 0 {
@@ -347,12 +381,34 @@ impl PacketState {
         let right_button = (status & BUTTON_RIGHT_MASK) != 0;
         let middle_button = (status & BUTTON_MIDDLE_MASK) != 0;
 
+        // The IntelliMouse Explorer reports the 4th and 5th buttons in the high bits of the wheel
+        // byte, which leaves only 4 bits for the wheel movement.
+        // Reference: <https://wiki.osdev.org/Mouse_Input>
+        let (z_delta, side_button, extra_button) = match self.mouse_type {
+            MouseType::Standard => (0, false, false),
+            MouseType::IntelliMouse => (self.packet[INDEX_WHEEL] as i8, false, false),
+            MouseType::IntelliMouseExplorer => {
+                let byte = self.packet[INDEX_WHEEL];
+                // Sign-extend the 4-bit wheel movement.
+                let z_delta = (((byte & EXPLORER_WHEEL_MASK) << 4) as i8) >> 4;
+                (
+                    z_delta,
+                    (byte & EXPLORER_BUTTON_SIDE_MASK) != 0,
+                    (byte & EXPLORER_BUTTON_EXTRA_MASK) != 0,
+                )
+            }
+        };
+
         let prev_left = self.prev_left;
         let prev_right = self.prev_right;
         let prev_middle = self.prev_middle;
+        let prev_side = self.prev_side;
+        let prev_extra = self.prev_extra;
         self.prev_left = left_button;
         self.prev_right = right_button;
         self.prev_middle = middle_button;
+        self.prev_side = side_button;
+        self.prev_extra = extra_button;
 
         if left_button != prev_left {
             events.push(InputEvent::from_key_and_status(
@@ -387,6 +443,28 @@ impl PacketState {
             ));
         }
 
+        if side_button != prev_side {
+            events.push(InputEvent::from_key_and_status(
+                KeyCode::BtnSide,
+                if side_button {
+                    KeyStatus::Pressed
+                } else {
+                    KeyStatus::Released
+                },
+            ));
+        }
+
+        if extra_button != prev_extra {
+            events.push(InputEvent::from_key_and_status(
+                KeyCode::BtnExtra,
+                if extra_button {
+                    KeyStatus::Pressed
+                } else {
+                    KeyStatus::Released
+                },
+            ));
+        }
+
         if x_delta != 0 {
             events.push(InputEvent::from_relative_move(RelCode::X, x_delta));
         }
@@ -395,14 +473,11 @@ impl PacketState {
             events.push(InputEvent::from_relative_move(RelCode::Y, -y_delta));
         }
 
-        if self.mouse_type == MouseType::IntelliMouse {
-            let z_delta = self.packet[INDEX_WHEEL] as i8;
-            if z_delta != 0 {
-                events.push(InputEvent::from_relative_move(
-                    RelCode::Wheel,
-                    -(z_delta as i32),
-                ));
-            }
+        if z_delta != 0 {
+            events.push(InputEvent::from_relative_move(
+                RelCode::Wheel,
+                -(z_delta as i32),
+            ));
         }
 
         // Add a sync event to indicate end of this input report.
@@ -158,6 +158,124 @@ impl RelCodeSet {
     }
 }
 
+/// Absolute axes.
+#[repr(u16)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AbsCode {
+    X = 0x00,
+    Y = 0x01,
+    Z = 0x02,
+    Rx = 0x03,
+    Ry = 0x04,
+    Rz = 0x05,
+    Throttle = 0x06,
+    Rudder = 0x07,
+    Wheel = 0x08,
+    Gas = 0x09,
+    Brake = 0x0a,
+    Hat0X = 0x10,
+    Hat0Y = 0x11,
+    Hat1X = 0x12,
+    Hat1Y = 0x13,
+    Hat2X = 0x14,
+    Hat2Y = 0x15,
+    Hat3X = 0x16,
+    Hat3Y = 0x17,
+    Pressure = 0x18,
+    Distance = 0x19,
+    TiltX = 0x1a,
+    TiltY = 0x1b,
+    ToolWidth = 0x1c,
+    Volume = 0x20,
+    Profile = 0x21,
+    Misc = 0x28,
+    // Multi-touch axes.
+    MtSlot = 0x2f,
+    MtTouchMajor = 0x30,
+    MtTouchMinor = 0x31,
+    MtWidthMajor = 0x32,
+    MtWidthMinor = 0x33,
+    MtOrientation = 0x34,
+    MtPositionX = 0x35,
+    MtPositionY = 0x36,
+    MtToolType = 0x37,
+    MtBlobId = 0x38,
+    MtTrackingId = 0x39,
+    MtPressure = 0x3a,
+    MtDistance = 0x3b,
+    MtToolX = 0x3c,
+    MtToolY = 0x3d,
+}
+/// The maximum value for absolute axes.
+const ABS_MAX: usize = 0x3f;
+/// The number of absolute axes.
+const ABS_COUNT: usize = ABS_MAX + 1;
+
+/// A set of [`AbsCode`] represented as a bitmap.
+#[derive(Debug, Clone)]
+pub struct AbsCodeSet(BitVec<u8>);
+
+impl Default for AbsCodeSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AbsCodeSet {
+    /// Creates an empty set.
+    pub fn new() -> Self {
+        Self(BitVec::repeat(false, ABS_COUNT))
+    }
+
+    /// Sets an absolute code in the set.
+    pub fn set(&mut self, abs_code: AbsCode) {
+        let index = abs_code as usize;
+        self.0.set(index, true);
+    }
+
+    /// Clears an absolute code from the set.
+    pub fn clear(&mut self, abs_code: AbsCode) {
+        let index = abs_code as usize;
+        self.0.set(index, false);
+    }
+
+    /// Checks if the set contains an absolute code.
+    pub fn contain(&self, abs_code: AbsCode) -> bool {
+        let index = abs_code as usize;
+        self.0.get(index).map(|b| *b).unwrap()
+    }
+
+    /// Returns the bitmap as a byte slice.
+    pub fn as_raw_slice(&self) -> &[u8] {
+        self.0.as_raw_slice()
+    }
+}
+
+bitflags! {
+    /// Input device properties.
+    ///
+    /// The properties tell the user space how to interpret the events (e.g., whether the
+    /// coordinates of a touch device map directly to the screen).
+    pub struct InputProps: u32 {
+        /// The device needs a pointer (e.g., touchpads).
+        const POINTER = 1 << 0x00;
+        /// The device is a direct input device (e.g., touchscreens).
+        const DIRECT = 1 << 0x01;
+        /// The device has buttons under the pad.
+        const BUTTONPAD = 1 << 0x02;
+        /// The device is a semi-multi-touch device.
+        const SEMI_MT = 1 << 0x03;
+        /// The device has soft buttons at the top of the pad.
+        const TOPBUTTONPAD = 1 << 0x04;
+        /// The device is a pointing stick.
+        const POINTING_STICK = 1 << 0x05;
+        /// The device is an accelerometer.
+        const ACCELEROMETER = 1 << 0x06;
+        /// The device is a pressure-sensitive touchpad.
+        const PRESSUREPAD = 1 << 0x07;
+    }
+}
+
 /// A set of [`KeyCode`] represented as a bitmap.
 #[derive(Debug, Clone)]
 pub struct KeyCodeSet(BitVec<u8>);
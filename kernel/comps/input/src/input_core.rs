@@ -8,7 +8,9 @@ use ostd::sync::{RwLock, WriteIrqDisabled};
 use crate::{
     InputDevice,
     input_dev::RegisteredInputDevice,
-    input_handler::{BoundInputHandler, InputHandlerClass},
+    input_handler::{
+        BoundInputHandler, BoundInputHandlers, GrabError, InputHandler, InputHandlerClass,
+    },
 };
 
 /// Registry entry for each registered device.
@@ -19,7 +21,7 @@ struct InputDeviceRegistry {
     /// The input device.
     device: Arc<dyn InputDevice>,
     /// Handlers connected to this device.
-    handlers: Arc<RwLock<Vec<BoundInputHandler>, WriteIrqDisabled>>,
+    handlers: Arc<RwLock<BoundInputHandlers, WriteIrqDisabled>>,
 }
 
 /// The core component of the input subsystem.
@@ -50,10 +52,14 @@ impl InputCore {
         for device_registry in self.devices.iter() {
             match handler_class.connect(device_registry.device.clone()) {
                 Ok(handler) => {
-                    device_registry.handlers.write().push(BoundInputHandler {
-                        handler,
-                        handler_class: handler_class.clone(),
-                    });
+                    device_registry
+                        .handlers
+                        .write()
+                        .all
+                        .push(BoundInputHandler {
+                            handler,
+                            handler_class: handler_class.clone(),
+                        });
                     log::info!(
                         "Input: successfully connected handler class {} to device {}",
                         handler_class.name(),
@@ -90,12 +96,20 @@ impl InputCore {
         for device_registry in self.devices.iter() {
             let mut handlers = device_registry.handlers.write();
             let Some(pos) = handlers
+                .all
                 .iter()
                 .position(|h| Arc::ptr_eq(&h.handler_class, &handler_class))
             else {
                 continue;
             };
-            let handler = handlers.swap_remove(pos);
+            let handler = handlers.all.swap_remove(pos);
+            if handlers
+                .grab
+                .as_ref()
+                .is_some_and(|grab| Arc::ptr_eq(grab, &handler.handler))
+            {
+                handlers.grab = None;
+            }
             drop(handlers);
             drop(handler);
 
@@ -136,7 +150,10 @@ impl InputCore {
                 }
             }
         }
-        let handlers = Arc::new(RwLock::new(connected_handlers));
+        let handlers = Arc::new(RwLock::new(BoundInputHandlers {
+            all: connected_handlers,
+            grab: None,
+        }));
 
         // Add the device registry.
         let new_registry = InputDeviceRegistry {
@@ -163,7 +180,7 @@ impl InputCore {
 
         // Take all handlers connected to this device and clear the list.
         let mut handlers = device_registry.handlers.write();
-        let bound_handlers = core::mem::take(&mut *handlers);
+        let bound_handlers = core::mem::take(&mut *handlers).all;
         drop(handlers);
 
         // Disconnect handler classes that were connected.
@@ -177,6 +194,58 @@ impl InputCore {
         Some(device_registry.device)
     }
 
+    /// Grabs an input device so that its events are delivered only to the given handler.
+    pub(crate) fn grab_device(
+        &self,
+        device: &Arc<dyn InputDevice>,
+        handler: &Arc<dyn InputHandler>,
+    ) -> Result<(), GrabError> {
+        let device_registry = self
+            .devices
+            .iter()
+            .find(|registry| Arc::ptr_eq(&registry.device, device))
+            .ok_or(GrabError::NotConnected)?;
+
+        let mut handlers = device_registry.handlers.write();
+        if !handlers
+            .all
+            .iter()
+            .any(|h| Arc::ptr_eq(&h.handler, handler))
+        {
+            return Err(GrabError::NotConnected);
+        }
+        if handlers.grab.is_some() {
+            return Err(GrabError::AlreadyGrabbed);
+        }
+        handlers.grab = Some(handler.clone());
+
+        Ok(())
+    }
+
+    /// Releases an input device if it has been grabbed by the given handler.
+    pub(crate) fn release_device(
+        &self,
+        device: &Arc<dyn InputDevice>,
+        handler: &Arc<dyn InputHandler>,
+    ) {
+        let Some(device_registry) = self
+            .devices
+            .iter()
+            .find(|registry| Arc::ptr_eq(&registry.device, device))
+        else {
+            return;
+        };
+
+        let mut handlers = device_registry.handlers.write();
+        if handlers
+            .grab
+            .as_ref()
+            .is_some_and(|grab| Arc::ptr_eq(grab, handler))
+        {
+            handlers.grab = None;
+        }
+    }
+
     /// Counts the number of registered devices.
     pub(crate) fn count_devices(&self) -> usize {
         self.devices.len()
@@ -1,13 +1,16 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use alloc::{sync::Arc, vec::Vec};
+use alloc::{collections::btree_map::BTreeMap, sync::Arc};
 use core::{any::Any, fmt::Debug};
 
 use ostd::sync::{RwLock, WriteIrqDisabled};
 
 use crate::{
-    event_type_codes::{EventTypes, KeyCode, KeyCodeSet, KeyStatus, RelCode, RelCodeSet, SynEvent},
-    input_handler::BoundInputHandler,
+    event_type_codes::{
+        AbsCode, AbsCodeSet, EventTypes, InputProps, KeyCode, KeyCodeSet, KeyStatus, RelCode,
+        RelCodeSet, SynEvent,
+    },
+    input_handler::BoundInputHandlers,
     unregister_device,
 };
 
@@ -20,7 +23,9 @@ pub enum InputEvent {
     Key(KeyCode, KeyStatus),
     /// Relative movement events (EV_REL)
     Relative(RelCode, i32),
-    // TODO: Add EV_ABS, EV_MSC, EV_SW, EV_LED, EV_SND, ... as needed
+    /// Absolute position events (EV_ABS)
+    Absolute(AbsCode, i32),
+    // TODO: Add EV_MSC, EV_SW, EV_LED, EV_SND, ... as needed
 }
 
 impl InputEvent {
@@ -39,6 +44,11 @@ impl InputEvent {
         Self::Relative(axis, value)
     }
 
+    /// Creates an absolute position event.
+    pub fn from_absolute_position(axis: AbsCode, value: i32) -> Self {
+        Self::Absolute(axis, value)
+    }
+
     /// Converts enum to raw Linux input event triplet (type, code, value).
     pub fn to_raw(&self) -> (u16, u16, i32) {
         match self {
@@ -51,6 +61,7 @@ impl InputEvent {
                 (EventTypes::KEY.as_index(), *key as u16, *status as i32)
             }
             InputEvent::Relative(axis, value) => (EventTypes::REL.as_index(), *axis as u16, *value),
+            InputEvent::Absolute(axis, value) => (EventTypes::ABS.as_index(), *axis as u16, *value),
         }
     }
 
@@ -60,6 +71,7 @@ impl InputEvent {
             InputEvent::Sync(_) => EventTypes::SYN,
             InputEvent::Key(_, _) => EventTypes::KEY,
             InputEvent::Relative(_, _) => EventTypes::REL,
+            InputEvent::Absolute(_, _) => EventTypes::ABS,
         }
     }
 }
@@ -134,6 +146,41 @@ impl InputId {
     pub const BUS_INTEL_ISHTP: u16 = 0x1F;
 }
 
+/// The parameters of an absolute axis.
+///
+/// This has the same layout as `struct input_absinfo` in Linux.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/input.h>
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, Pod)]
+pub struct AbsInfo {
+    /// The latest reported value of the axis.
+    pub value: i32,
+    /// The minimum value of the axis.
+    pub minimum: i32,
+    /// The maximum value of the axis.
+    pub maximum: i32,
+    /// The fuzz value that is used to filter noise from the event stream.
+    pub fuzz: i32,
+    /// The values within this value are discarded and reported as zero.
+    pub flat: i32,
+    /// The resolution of the axis (in units per millimeter or units per radian).
+    pub resolution: i32,
+}
+
+impl AbsInfo {
+    /// Creates a new `AbsInfo` with the specified range.
+    ///
+    /// The initial value, the fuzz, the flat, and the resolution are all zero.
+    pub fn new(minimum: i32, maximum: i32) -> Self {
+        Self {
+            minimum,
+            maximum,
+            ..Self::default()
+        }
+    }
+}
+
 /// Input device capability bitmaps.
 #[derive(Debug, Clone)]
 pub struct InputCapability {
@@ -143,7 +190,13 @@ pub struct InputCapability {
     supported_keys: KeyCodeSet,
     /// Supported relative axis codes.
     supported_relative_axes: RelCodeSet,
-    // TODO: Add supported_absolute_axes, supported_misc, etc.
+    /// Supported absolute axis codes.
+    supported_absolute_axes: AbsCodeSet,
+    /// Parameters of the supported absolute axes.
+    absolute_axis_infos: BTreeMap<AbsCode, AbsInfo>,
+    /// Device properties.
+    properties: InputProps,
+    // TODO: Add supported_misc, etc.
 }
 
 impl Default for InputCapability {
@@ -159,6 +212,9 @@ impl InputCapability {
             supported_event_types: EventTypes::new(),
             supported_keys: KeyCodeSet::new(),
             supported_relative_axes: RelCodeSet::new(),
+            supported_absolute_axes: AbsCodeSet::new(),
+            absolute_axis_infos: BTreeMap::new(),
+            properties: InputProps::empty(),
         }
     }
 
@@ -223,6 +279,46 @@ impl InputCapability {
         self.supported_relative_axes.clear(rel_code);
     }
 
+    /// Sets absolute axis capability with the parameters of the axis.
+    pub fn set_supported_absolute_axis(&mut self, abs_code: AbsCode, abs_info: AbsInfo) {
+        self.supported_absolute_axes.set(abs_code);
+        self.absolute_axis_infos.insert(abs_code, abs_info);
+        self.set_supported_event_type(EventTypes::ABS);
+    }
+
+    /// Checks if an absolute code is supported.
+    pub fn support_absolute_axis(&self, abs_code: AbsCode) -> bool {
+        self.supported_absolute_axes.contain(abs_code)
+    }
+
+    /// Removes support for an absolute code.
+    pub fn clear_supported_absolute_axis(&mut self, abs_code: AbsCode) {
+        self.supported_absolute_axes.clear(abs_code);
+        self.absolute_axis_infos.remove(&abs_code);
+    }
+
+    /// Returns the parameters of an absolute axis if the axis is supported.
+    pub fn absolute_axis_info(&self, abs_code: AbsCode) -> Option<&AbsInfo> {
+        self.absolute_axis_infos.get(&abs_code)
+    }
+
+    /// Returns the parameters of all the supported absolute axes.
+    pub fn absolute_axis_infos(&self) -> impl Iterator<Item = (AbsCode, &AbsInfo)> {
+        self.absolute_axis_infos
+            .iter()
+            .map(|(abs_code, abs_info)| (*abs_code, abs_info))
+    }
+
+    /// Sets device properties.
+    pub fn set_properties(&mut self, properties: InputProps) {
+        self.properties |= properties;
+    }
+
+    /// Returns the device properties.
+    pub fn properties(&self) -> InputProps {
+        self.properties
+    }
+
     /// Returns the supported event types as a bitmap.
     pub fn event_types_bits(&self) -> u32 {
         self.supported_event_types.bits()
@@ -237,6 +333,11 @@ impl InputCapability {
     pub fn supported_relative_axes_bitmap(&self) -> &[u8] {
         self.supported_relative_axes.as_raw_slice()
     }
+
+    /// Returns the supported absolute axes bitmap as bytes.
+    pub fn supported_absolute_axes_bitmap(&self) -> &[u8] {
+        self.supported_absolute_axes.as_raw_slice()
+    }
 }
 
 pub trait InputDevice: Send + Sync + Any + Debug {
@@ -279,13 +380,13 @@ pub struct RegisteredInputDevice {
     /// Original device.
     device: Arc<dyn InputDevice>,
     /// Reference to bound handlers for direct event dispatch.
-    handlers: Arc<RwLock<Vec<BoundInputHandler>, WriteIrqDisabled>>,
+    handlers: Arc<RwLock<BoundInputHandlers, WriteIrqDisabled>>,
 }
 
 impl RegisteredInputDevice {
     pub(crate) fn new(
         device: Arc<dyn InputDevice>,
-        handlers: Arc<RwLock<Vec<BoundInputHandler>, WriteIrqDisabled>>,
+        handlers: Arc<RwLock<BoundInputHandlers, WriteIrqDisabled>>,
     ) -> Self {
         Self { device, handlers }
     }
@@ -299,7 +400,12 @@ impl RegisteredInputDevice {
         );
 
         let handlers = self.handlers.read();
-        if handlers.is_empty() {
+        if let Some(grab) = handlers.grab.as_ref() {
+            grab.handle_events(events);
+            return;
+        }
+
+        if handlers.all.is_empty() {
             log::debug!(
                 "Input: dropped events from device {} because it has no handlers",
                 self.device.name()
@@ -307,7 +413,7 @@ impl RegisteredInputDevice {
             return;
         }
 
-        for bound_handler in handlers.iter() {
+        for bound_handler in handlers.all.iter() {
             bound_handler.handler.handle_events(events);
         }
     }
@@ -319,7 +425,7 @@ impl RegisteredInputDevice {
 
     /// Counts the number of connected handlers.
     pub fn count_handlers(&self) -> usize {
-        self.handlers.read().len()
+        self.handlers.read().all.len()
     }
 
     /// Checks if the device supports a specific event based on its capabilities.
@@ -335,6 +441,10 @@ impl RegisteredInputDevice {
                 capability.support_event_type(EventTypes::REL)
                     && capability.support_relative_axis(*rel_event)
             }
+            InputEvent::Absolute(abs_event, _) => {
+                capability.support_event_type(EventTypes::ABS)
+                    && capability.support_absolute_axis(*abs_event)
+            }
         }
     }
 }
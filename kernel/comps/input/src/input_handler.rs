@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use alloc::sync::Arc;
+use alloc::{sync::Arc, vec::Vec};
 use core::{any::Any, fmt::Debug};
 
 use crate::{InputDevice, input_dev::InputEvent, unregister_handler_class};
@@ -18,6 +18,15 @@ pub enum ConnectError {
     InternalError,
 }
 
+/// Errors that can occur when grabbing an input device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrabError {
+    /// Device has already been grabbed by a handler.
+    AlreadyGrabbed,
+    /// Handler is not connected to the device.
+    NotConnected,
+}
+
 /// A trait that represents an input handler class.
 ///
 /// Once registered to the input core (via [`register_handler_class`]), the
@@ -61,6 +70,17 @@ pub(crate) struct BoundInputHandler {
     pub(crate) handler_class: Arc<dyn InputHandlerClass>,
 }
 
+/// All the input handlers bound to an input device.
+#[derive(Debug, Default)]
+pub(crate) struct BoundInputHandlers {
+    /// The handlers connected to the device.
+    pub(crate) all: Vec<BoundInputHandler>,
+    /// The handler that has grabbed the device.
+    ///
+    /// While the device is grabbed, its events are delivered only to this handler.
+    pub(crate) grab: Option<Arc<dyn InputHandler>>,
+}
+
 /// Registered input handler class that can create handlers.
 #[derive(Debug)]
 pub struct RegisteredInputHandlerClass(pub(crate) Arc<dyn InputHandlerClass>);
@@ -52,7 +52,7 @@ use spin::Once;
 use self::input_core::InputCore;
 use crate::{
     input_dev::{InputDevice, RegisteredInputDevice},
-    input_handler::{InputHandlerClass, RegisteredInputHandlerClass},
+    input_handler::{GrabError, InputHandler, InputHandlerClass, RegisteredInputHandlerClass},
 };
 
 /// Registers a handler class.
@@ -90,6 +90,23 @@ pub(crate) fn unregister_device(device: &Arc<dyn InputDevice>) -> Option<Arc<dyn
     component.input_core.lock().unregister_device(device)
 }
 
+/// Grabs an input device so that its events are delivered only to the given handler.
+///
+/// The handler must be connected to the device. At most one handler can grab the device at a time.
+pub fn grab_device(
+    device: &Arc<dyn InputDevice>,
+    handler: &Arc<dyn InputHandler>,
+) -> Result<(), GrabError> {
+    let component = COMPONENT.get().unwrap();
+    component.input_core.lock().grab_device(device, handler)
+}
+
+/// Releases an input device if it has been grabbed by the given handler.
+pub fn release_device(device: &Arc<dyn InputDevice>, handler: &Arc<dyn InputHandler>) {
+    let component = COMPONENT.get().unwrap();
+    component.input_core.lock().release_device(device, handler)
+}
+
 /// Counts the number of registered devices.
 pub fn count_devices() -> usize {
     let component = COMPONENT.get().unwrap();
@@ -1,28 +1,83 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use core::time::Duration;
+use core::{fmt::Write, time::Duration};
 
-use log::{Metadata, Record};
+use log::{Level, Metadata, Record};
 use ostd::timer::Jiffies;
 
+use crate::{
+    console_level, dynamic_debug,
+    kmsg::{self, KMSG_FACILITY_KERN, KMSG_TEXT_MAX},
+};
+
 /// The logger used for Asterinas.
 struct AsterLogger;
 
 static LOGGER: AsterLogger = AsterLogger;
 
 impl log::Log for AsterLogger {
-    fn enabled(&self, _metadata: &Metadata) -> bool {
-        true
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        dynamic_debug::is_enabled(metadata.level(), metadata.target(), None)
     }
 
     fn log(&self, record: &Record) {
+        // The target is the module path unless it is explicitly specified.
+        if !dynamic_debug::is_enabled(record.level(), record.target(), record.file()) {
+            return;
+        }
+
         let timestamp = Jiffies::elapsed().as_duration();
-        print_logs(record, &timestamp);
+        store_logs(record, &timestamp);
+        if console_level::should_print(syslog_level(record.level())) {
+            print_logs(record, &timestamp);
+        }
     }
 
     fn flush(&self) {}
 }
 
+/// Stores the record in the kernel message ring buffer.
+fn store_logs(record: &Record, timestamp: &Duration) {
+    // Format the text on the stack, as the heap allocator may log messages.
+    let mut text = TextBuffer {
+        bytes: [0; KMSG_TEXT_MAX],
+        len: 0,
+    };
+    let _ = write!(text, "{}", record.args());
+
+    kmsg::append(
+        KMSG_FACILITY_KERN,
+        syslog_level(record.level()),
+        *timestamp,
+        &text.bytes[..text.len],
+    );
+}
+
+/// Converts the level of the `log` crate to the syslog level.
+fn syslog_level(level: Level) -> u8 {
+    match level {
+        Level::Error => 3,                // KERN_ERR
+        Level::Warn => 4,                 // KERN_WARNING
+        Level::Info => 6,                 // KERN_INFO
+        Level::Debug | Level::Trace => 7, // KERN_DEBUG
+    }
+}
+
+/// A fixed-size buffer that silently truncates the text that does not fit.
+struct TextBuffer {
+    bytes: [u8; KMSG_TEXT_MAX],
+    len: usize,
+}
+
+impl Write for TextBuffer {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let len = s.len().min(KMSG_TEXT_MAX - self.len);
+        self.bytes[self.len..self.len + len].copy_from_slice(&s.as_bytes()[..len]);
+        self.len += len;
+        Ok(())
+    }
+}
+
 #[cfg(feature = "log_color")]
 fn print_logs(record: &Record, timestamp: &Duration) {
     use owo_colors::Style;
@@ -62,5 +117,6 @@ fn print_logs(record: &Record, timestamp: &Duration) {
 }
 
 pub(super) fn init() {
+    dynamic_debug::init();
     ostd::logger::inject_logger(&LOGGER);
 }
@@ -0,0 +1,108 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The log levels that decide which records are printed to the console.
+//!
+//! These are the four values of the `kernel.printk` sysctl in Linux. Records are always stored in
+//! the kernel message ring buffer regardless of these levels.
+
+use core::sync::atomic::{AtomicU8, Ordering};
+
+/// The console log level that prints records of all levels.
+///
+/// Unlike Linux, whose default console log level (7) hides `KERN_DEBUG` records, this is the
+/// default because records are already filtered by `ostd.log_level` before they reach the logger.
+/// So a developer who asks for debug logs gets them on the console.
+const CONSOLE_LOGLEVEL_DEBUG: u8 = 8;
+
+/// The value of [`SAVED_CONSOLE_LOGLEVEL`] when no console log level is saved.
+const NO_SAVED_LOGLEVEL: u8 = u8::MAX;
+
+/// The console log level.
+///
+/// Only records whose level is numerically less than the console log level are printed.
+static CONSOLE_LOGLEVEL: AtomicU8 = AtomicU8::new(CONSOLE_LOGLEVEL_DEBUG);
+/// The level of messages written to `/dev/kmsg` without an explicit level.
+static DEFAULT_MESSAGE_LOGLEVEL: AtomicU8 = AtomicU8::new(4); // KERN_WARNING
+/// The lowest value to which the console log level can be set by `syslog(2)`.
+static MINIMUM_CONSOLE_LOGLEVEL: AtomicU8 = AtomicU8::new(1); // KERN_ALERT
+/// The console log level at boot.
+static DEFAULT_CONSOLE_LOGLEVEL: AtomicU8 = AtomicU8::new(CONSOLE_LOGLEVEL_DEBUG);
+/// The console log level before [`console_off`] is called.
+static SAVED_CONSOLE_LOGLEVEL: AtomicU8 = AtomicU8::new(NO_SAVED_LOGLEVEL);
+
+/// Returns whether a record of the level should be printed to the console.
+pub(crate) fn should_print(level: u8) -> bool {
+    level < CONSOLE_LOGLEVEL.load(Ordering::Relaxed)
+}
+
+/// Returns the console log level.
+pub fn console_loglevel() -> u8 {
+    CONSOLE_LOGLEVEL.load(Ordering::Relaxed)
+}
+
+/// Sets the console log level.
+pub fn set_console_loglevel(level: u8) {
+    CONSOLE_LOGLEVEL.store(level, Ordering::Relaxed);
+}
+
+/// Returns the level of messages written to `/dev/kmsg` without an explicit level.
+pub fn default_message_loglevel() -> u8 {
+    DEFAULT_MESSAGE_LOGLEVEL.load(Ordering::Relaxed)
+}
+
+/// Sets the level of messages written to `/dev/kmsg` without an explicit level.
+pub fn set_default_message_loglevel(level: u8) {
+    DEFAULT_MESSAGE_LOGLEVEL.store(level, Ordering::Relaxed);
+}
+
+/// Returns the lowest value to which the console log level can be set by `syslog(2)`.
+pub fn minimum_console_loglevel() -> u8 {
+    MINIMUM_CONSOLE_LOGLEVEL.load(Ordering::Relaxed)
+}
+
+/// Sets the lowest value to which the console log level can be set by `syslog(2)`.
+pub fn set_minimum_console_loglevel(level: u8) {
+    MINIMUM_CONSOLE_LOGLEVEL.store(level, Ordering::Relaxed);
+}
+
+/// Returns the console log level at boot.
+pub fn default_console_loglevel() -> u8 {
+    DEFAULT_CONSOLE_LOGLEVEL.load(Ordering::Relaxed)
+}
+
+/// Sets the console log level at boot.
+///
+/// This only records the value, since the system has already booted.
+pub fn set_default_console_loglevel(level: u8) {
+    DEFAULT_CONSOLE_LOGLEVEL.store(level, Ordering::Relaxed);
+}
+
+/// Silences the console except for the most urgent records (`SYSLOG_ACTION_CONSOLE_OFF`).
+///
+/// The current console log level is saved and can be restored by [`console_on`].
+pub fn console_off() {
+    let _ = SAVED_CONSOLE_LOGLEVEL.compare_exchange(
+        NO_SAVED_LOGLEVEL,
+        console_loglevel(),
+        Ordering::Relaxed,
+        Ordering::Relaxed,
+    );
+    set_console_loglevel(minimum_console_loglevel());
+}
+
+/// Restores the console log level saved by [`console_off`] (`SYSLOG_ACTION_CONSOLE_ON`).
+pub fn console_on() {
+    let saved_level = SAVED_CONSOLE_LOGLEVEL.swap(NO_SAVED_LOGLEVEL, Ordering::Relaxed);
+    if saved_level != NO_SAVED_LOGLEVEL {
+        set_console_loglevel(saved_level);
+    }
+}
+
+/// Changes the console log level on behalf of `SYSLOG_ACTION_CONSOLE_LEVEL`.
+///
+/// The level is raised to the minimum console log level if it is lower, and any level saved by
+/// [`console_off`] is discarded.
+pub fn change_console_loglevel(level: u8) {
+    set_console_loglevel(level.max(minimum_console_loglevel()));
+    SAVED_CONSOLE_LOGLEVEL.store(NO_SAVED_LOGLEVEL, Ordering::Relaxed);
+}
@@ -0,0 +1,246 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Dynamic debug.
+//!
+//! Records are normally filtered by the log level given by `ostd.log_level`. Dynamic debug
+//! enables the records of all levels at runtime for the modules or the source files that match
+//! some rules, so that verbose logging of a subsystem can be turned on without recompiling or
+//! rebooting the kernel.
+//!
+//! The rules are managed with a subset of the query language of Linux's dynamic debug. A query
+//! consists of optional match specifications followed by a flag specification, e.g.,
+//!
+//! ```text
+//! module aster_kernel::net +p
+//! file vm/vmar/mod.rs +p
+//! module aster_kernel::net::socket::ip -p
+//! -p
+//! ```
+//!
+//! A module matches itself and all its submodules, and a file matches if its path ends with the
+//! given path. A later query overrides the earlier ones that it overlaps with, and a query
+//! without any match specifications applies to everything.
+//!
+//! Reference: <https://docs.kernel.org/admin-guide/dynamic-debug-howto.html>
+
+use alloc::{string::String, sync::Arc, vec::Vec};
+use core::fmt::{self, Write};
+
+use log::{Level, LevelFilter};
+use ostd::sync::{LocalIrqDisabled, Mutex, SpinLock};
+use spin::Once;
+
+/// The log level given by `ostd.log_level`, which applies to records that match no rules.
+static BASE_LEVEL: Once<LevelFilter> = Once::new();
+
+/// The rules, where a later rule takes precedence over an earlier one.
+///
+/// The rules are shared via an [`Arc`] so that no memory is allocated while holding the lock,
+/// since the heap allocator may log messages. `None` means that there are no rules.
+static RULES: SpinLock<Option<Arc<Vec<DynDebugRule>>>, LocalIrqDisabled> = SpinLock::new(None);
+
+/// The lock that serializes updates of [`RULES`].
+static UPDATE_LOCK: Mutex<()> = Mutex::new(());
+
+/// A rule that enables or disables verbose logging of the matching modules and files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DynDebugRule {
+    module: Option<String>,
+    file: Option<String>,
+    is_enabled: bool,
+}
+
+impl DynDebugRule {
+    fn matches(&self, module: &str, file: Option<&str>) -> bool {
+        if let Some(rule_module) = self.module.as_deref()
+            && !is_module_or_submodule(module, rule_module)
+        {
+            return false;
+        }
+
+        if let Some(rule_file) = self.file.as_deref()
+            && !file.is_some_and(|file| file.ends_with(rule_file))
+        {
+            return false;
+        }
+
+        true
+    }
+
+    /// Returns whether this rule only matches records that `other` also matches.
+    fn is_covered_by(&self, other: &DynDebugRule) -> bool {
+        let is_module_covered = match (self.module.as_deref(), other.module.as_deref()) {
+            (_, None) => true,
+            (Some(module), Some(other_module)) => is_module_or_submodule(module, other_module),
+            (None, Some(_)) => false,
+        };
+        let is_file_covered = match (self.file.as_deref(), other.file.as_deref()) {
+            (_, None) => true,
+            (Some(file), Some(other_file)) => file.ends_with(other_file),
+            (None, Some(_)) => false,
+        };
+
+        is_module_covered && is_file_covered
+    }
+}
+
+impl fmt::Display for DynDebugRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(module) = self.module.as_deref() {
+            write!(f, "module {} ", module)?;
+        }
+        if let Some(file) = self.file.as_deref() {
+            write!(f, "file {} ", file)?;
+        }
+        if self.is_enabled {
+            write!(f, "=p")
+        } else {
+            write!(f, "=_")
+        }
+    }
+}
+
+/// An error that occurs when parsing a dynamic debug query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DynDebugError {
+    /// A match specification has no value.
+    InvalidMatch,
+    /// The flag specification is missing, is malformed, or contains unsupported flags.
+    InvalidFlags,
+}
+
+/// Applies one or more queries, which are separated by newlines or semicolons.
+///
+/// Either all queries are applied or, if any of them is invalid, none of them.
+pub fn apply_queries(queries: &str) -> Result<(), DynDebugError> {
+    let new_rules = queries
+        .split(['\n', ';'])
+        .map(str::trim)
+        .filter(|query| !query.is_empty() && !query.starts_with('#'))
+        .map(parse_query)
+        .collect::<Result<Vec<_>, _>>()?;
+    if new_rules.is_empty() {
+        return Ok(());
+    }
+
+    let _update_guard = UPDATE_LOCK.lock();
+
+    let old_rules = RULES.lock().clone();
+    let mut rules = old_rules.map(|rules| (*rules).clone()).unwrap_or_default();
+    for new_rule in new_rules {
+        rules.retain(|rule| !rule.is_covered_by(&new_rule));
+        // A disabling rule that overrides nothing is redundant.
+        if new_rule.is_enabled || rules.iter().any(|rule| rule.is_enabled) {
+            rules.push(new_rule);
+        }
+    }
+
+    let has_rules = !rules.is_empty();
+    let new_rules = has_rules.then(|| Arc::new(rules));
+    let old_rules = core::mem::replace(&mut *RULES.lock(), new_rules);
+    drop(old_rules);
+
+    // Let the records below the base level reach the logger only if some rules may enable them.
+    if has_rules {
+        log::set_max_level(LevelFilter::Trace);
+    } else {
+        log::set_max_level(base_level());
+    }
+
+    Ok(())
+}
+
+/// Writes the current rules, one per line, in the query language.
+pub fn write_rules(f: &mut dyn Write) -> fmt::Result {
+    let Some(rules) = RULES.lock().clone() else {
+        return Ok(());
+    };
+    for rule in rules.iter() {
+        writeln!(f, "{}", rule)?;
+    }
+    Ok(())
+}
+
+/// Returns whether a record from the module and the file should be logged.
+pub(crate) fn is_enabled(level: Level, module: &str, file: Option<&str>) -> bool {
+    if level <= base_level() {
+        return true;
+    }
+    let Some(rules) = RULES.lock().clone() else {
+        return false;
+    };
+
+    rules
+        .iter()
+        .rev()
+        .find(|rule| rule.matches(module, file))
+        .is_some_and(|rule| rule.is_enabled)
+}
+
+pub(crate) fn init() {
+    BASE_LEVEL.call_once(log::max_level);
+}
+
+fn base_level() -> LevelFilter {
+    BASE_LEVEL.get().copied().unwrap_or(LevelFilter::Off)
+}
+
+fn is_module_or_submodule(module: &str, parent: &str) -> bool {
+    module
+        .strip_prefix(parent)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
+
+fn parse_query(query: &str) -> Result<DynDebugRule, DynDebugError> {
+    let mut words = query.split_whitespace();
+    let mut rule = DynDebugRule {
+        module: None,
+        file: None,
+        is_enabled: false,
+    };
+
+    loop {
+        let Some(word) = words.next() else {
+            return Err(DynDebugError::InvalidFlags);
+        };
+
+        let value = match word {
+            "module" | "file" => words.next().ok_or(DynDebugError::InvalidMatch)?,
+            flags => {
+                rule.is_enabled = parse_flags(flags)?;
+                break;
+            }
+        };
+        if word == "module" {
+            rule.module = Some(value.into());
+        } else {
+            rule.file = Some(value.into());
+        }
+    }
+
+    if words.next().is_some() {
+        return Err(DynDebugError::InvalidFlags);
+    }
+
+    Ok(rule)
+}
+
+/// Parses a flag specification and returns whether it enables printing.
+///
+/// Only the `p` flag (printing) is supported.
+fn parse_flags(flags: &str) -> Result<bool, DynDebugError> {
+    let (op, flags) = flags
+        .split_at_checked(1)
+        .ok_or(DynDebugError::InvalidFlags)?;
+    if !flags.chars().all(|flag| flag == 'p' || flag == '_') {
+        return Err(DynDebugError::InvalidFlags);
+    }
+    let has_p = flags.contains('p');
+
+    match op {
+        "+" if has_p => Ok(true),
+        "-" if has_p => Ok(false),
+        "=" => Ok(has_p),
+        _ => Err(DynDebugError::InvalidFlags),
+    }
+}
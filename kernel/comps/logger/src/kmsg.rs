@@ -0,0 +1,330 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The kernel message ring buffer.
+//!
+//! Every kernel log record, as well as every message written to `/dev/kmsg` from user space, is
+//! stored in a fixed-size ring buffer together with its facility, level, timestamp, and sequence
+//! number. When the buffer is full, the oldest records are dropped to make room for new ones.
+//!
+//! The buffer is statically allocated so that appending records never touches the heap. This is
+//! important because the heap allocator may log messages itself.
+
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use ostd::sync::{LocalIrqDisabled, SpinLock};
+use spin::Once;
+
+/// The size of the ring buffer in bytes.
+///
+/// This is the same as the default `CONFIG_LOG_BUF_SHIFT` (17) in Linux.
+pub const KMSG_BUF_LEN: usize = 1 << 17;
+
+/// The maximum length of the text of a single record.
+///
+/// Longer texts are truncated. This matches `PRINTKRB_RECORD_MAX` in Linux.
+pub const KMSG_TEXT_MAX: usize = 1024 - 32;
+
+/// The facility of messages generated by the kernel (`LOG_KERN`).
+pub const KMSG_FACILITY_KERN: u8 = 0;
+/// The facility of messages generated by user space (`LOG_USER`).
+pub const KMSG_FACILITY_USER: u8 = 1;
+
+/// The function that is called after a record is appended.
+///
+/// The function may be called in any context, including the interrupt context.
+pub static KMSG_APPEND_HANDLER_FN: Once<fn()> = Once::new();
+
+static KMSG_BUF: SpinLock<KmsgBuffer, LocalIrqDisabled> = SpinLock::new(KmsgBuffer::new());
+
+/// A record read from the kernel message ring buffer.
+#[derive(Debug)]
+pub struct KmsgRecord {
+    seq: u64,
+    facility: u8,
+    level: u8,
+    timestamp: Duration,
+    text: Vec<u8>,
+}
+
+impl KmsgRecord {
+    /// Returns the sequence number.
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// Returns the syslog facility (e.g., [`KMSG_FACILITY_KERN`]).
+    pub fn facility(&self) -> u8 {
+        self.facility
+    }
+
+    /// Returns the syslog level, ranging from 0 (`KERN_EMERG`) to 7 (`KERN_DEBUG`).
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    /// Returns the syslog priority, which combines the facility and the level.
+    pub fn priority(&self) -> u32 {
+        ((self.facility as u32) << 3) | self.level as u32
+    }
+
+    /// Returns the time elapsed since boot when the record was appended.
+    pub fn timestamp(&self) -> Duration {
+        self.timestamp
+    }
+
+    /// Returns the text, which is not terminated by a newline.
+    pub fn text(&self) -> &[u8] {
+        &self.text
+    }
+}
+
+/// A position in the kernel message ring buffer.
+#[derive(Debug, Clone, Copy)]
+pub struct KmsgCursor {
+    seq: u64,
+    offset: usize,
+}
+
+impl KmsgCursor {
+    /// Creates a cursor that points to the first record appended since boot.
+    ///
+    /// If the record has been dropped, reading from the cursor moves it to the oldest record.
+    pub const fn new() -> Self {
+        Self { seq: 0, offset: 0 }
+    }
+
+    /// Returns a cursor that points to the oldest record.
+    pub fn first() -> Self {
+        let buf = KMSG_BUF.lock();
+        Self {
+            seq: buf.first_seq,
+            offset: buf.head,
+        }
+    }
+
+    /// Returns a cursor that points past the newest record.
+    pub fn end() -> Self {
+        let buf = KMSG_BUF.lock();
+        Self {
+            seq: buf.next_seq,
+            offset: buf.tail,
+        }
+    }
+
+    /// Returns a cursor that points to the oldest record that has not been cleared.
+    ///
+    /// See [`clear`] for how records are cleared.
+    pub fn after_clear() -> Self {
+        let buf = KMSG_BUF.lock();
+        if buf.clear_seq < buf.first_seq {
+            return Self {
+                seq: buf.first_seq,
+                offset: buf.head,
+            };
+        }
+
+        Self {
+            seq: buf.clear_seq,
+            offset: buf.clear_offset,
+        }
+    }
+
+    /// Returns the sequence number of the record that the cursor points to.
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// Returns whether there are records at or after the cursor.
+    pub fn has_record(&self) -> bool {
+        self.seq < KMSG_BUF.lock().next_seq
+    }
+
+    /// Returns whether the record that the cursor points to has been dropped.
+    pub fn is_overwritten(&self) -> bool {
+        self.seq < KMSG_BUF.lock().first_seq
+    }
+
+    /// Reads the record that the cursor points to and advances the cursor.
+    pub fn read_record(&mut self) -> Result<KmsgRecord, KmsgError> {
+        // Allocate the memory before taking the lock, as the heap allocator may log messages.
+        let mut text = Vec::with_capacity(KMSG_TEXT_MAX);
+
+        let buf = KMSG_BUF.lock();
+        if self.seq < buf.first_seq {
+            self.seq = buf.first_seq;
+            self.offset = buf.head;
+            return Err(KmsgError::Overwritten);
+        }
+        if self.seq == buf.next_seq {
+            return Err(KmsgError::NoRecord);
+        }
+
+        let header = buf.read_header(self.offset);
+        buf.read_bytes(
+            (self.offset + RecordHeader::LEN) % KMSG_BUF_LEN,
+            header.text_len as usize,
+            &mut text,
+        );
+
+        let record = KmsgRecord {
+            seq: self.seq,
+            facility: (header.priority >> 3) as u8,
+            level: (header.priority & 0x7) as u8,
+            timestamp: Duration::from_micros(header.timestamp_us),
+            text,
+        };
+        self.seq += 1;
+        self.offset = (self.offset + header.record_len()) % KMSG_BUF_LEN;
+
+        Ok(record)
+    }
+}
+
+impl Default for KmsgCursor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An error that occurs when reading the kernel message ring buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KmsgError {
+    /// The record has been dropped to make room for newer records.
+    ///
+    /// The cursor is moved to the oldest record that is still available.
+    Overwritten,
+    /// No record has been appended at the position yet.
+    NoRecord,
+}
+
+/// Appends a record to the kernel message ring buffer.
+///
+/// The text is truncated to [`KMSG_TEXT_MAX`] bytes.
+pub fn append(facility: u8, level: u8, timestamp: Duration, text: &[u8]) {
+    let text = &text[..text.len().min(KMSG_TEXT_MAX)];
+    let header = RecordHeader {
+        timestamp_us: timestamp.as_micros() as u64,
+        text_len: text.len() as u16,
+        priority: ((facility as u16) << 3) | (level & 0x7) as u16,
+    };
+
+    KMSG_BUF.lock().push(&header, text);
+
+    if let Some(handler) = KMSG_APPEND_HANDLER_FN.get() {
+        handler();
+    }
+}
+
+/// Clears the ring buffer.
+///
+/// Cleared records are not dropped; they are just skipped by [`KmsgCursor::after_clear`]. This
+/// is the semantics of `SYSLOG_ACTION_CLEAR`.
+pub fn clear() {
+    let mut buf = KMSG_BUF.lock();
+    buf.clear_seq = buf.next_seq;
+    buf.clear_offset = buf.tail;
+}
+
+struct KmsgBuffer {
+    data: [u8; KMSG_BUF_LEN],
+    /// The byte offset of the oldest record.
+    head: usize,
+    /// The byte offset at which the next record will be written.
+    tail: usize,
+    /// The number of bytes occupied by the records.
+    used: usize,
+    /// The sequence number of the oldest record.
+    first_seq: u64,
+    /// The sequence number of the next record to be appended.
+    next_seq: u64,
+    /// The sequence number of the oldest record that has not been cleared.
+    clear_seq: u64,
+    /// The byte offset of the record at `clear_seq`, if the record still exists.
+    clear_offset: usize,
+}
+
+impl KmsgBuffer {
+    const fn new() -> Self {
+        Self {
+            data: [0; KMSG_BUF_LEN],
+            head: 0,
+            tail: 0,
+            used: 0,
+            first_seq: 0,
+            next_seq: 0,
+            clear_seq: 0,
+            clear_offset: 0,
+        }
+    }
+
+    fn push(&mut self, header: &RecordHeader, text: &[u8]) {
+        let record_len = header.record_len();
+
+        while KMSG_BUF_LEN - self.used < record_len {
+            let oldest_len = self.read_header(self.head).record_len();
+            self.head = (self.head + oldest_len) % KMSG_BUF_LEN;
+            self.used -= oldest_len;
+            self.first_seq += 1;
+        }
+
+        let tail = self.tail;
+        self.write_bytes(tail, &header.to_bytes());
+        self.write_bytes((tail + RecordHeader::LEN) % KMSG_BUF_LEN, text);
+
+        self.tail = (tail + record_len) % KMSG_BUF_LEN;
+        self.used += record_len;
+        self.next_seq += 1;
+    }
+
+    fn read_header(&self, offset: usize) -> RecordHeader {
+        let mut bytes = [0u8; RecordHeader::LEN];
+        for (index, byte) in bytes.iter_mut().enumerate() {
+            *byte = self.data[(offset + index) % KMSG_BUF_LEN];
+        }
+        RecordHeader::from_bytes(&bytes)
+    }
+
+    fn read_bytes(&self, offset: usize, len: usize, out: &mut Vec<u8>) {
+        let first_len = len.min(KMSG_BUF_LEN - offset);
+        out.extend_from_slice(&self.data[offset..offset + first_len]);
+        out.extend_from_slice(&self.data[..len - first_len]);
+    }
+
+    fn write_bytes(&mut self, offset: usize, bytes: &[u8]) {
+        let first_len = bytes.len().min(KMSG_BUF_LEN - offset);
+        self.data[offset..offset + first_len].copy_from_slice(&bytes[..first_len]);
+        self.data[..bytes.len() - first_len].copy_from_slice(&bytes[first_len..]);
+    }
+}
+
+/// The header that precedes the text of each record in the ring buffer.
+struct RecordHeader {
+    timestamp_us: u64,
+    text_len: u16,
+    priority: u16,
+}
+
+impl RecordHeader {
+    const LEN: usize = size_of::<u64>() + size_of::<u16>() * 2;
+
+    fn record_len(&self) -> usize {
+        Self::LEN + self.text_len as usize
+    }
+
+    fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        bytes[0..8].copy_from_slice(&self.timestamp_us.to_ne_bytes());
+        bytes[8..10].copy_from_slice(&self.text_len.to_ne_bytes());
+        bytes[10..12].copy_from_slice(&self.priority.to_ne_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8; Self::LEN]) -> Self {
+        Self {
+            timestamp_us: u64::from_ne_bytes(bytes[0..8].try_into().unwrap()),
+            text_len: u16::from_ne_bytes(bytes[8..10].try_into().unwrap()),
+            priority: u16::from_ne_bytes(bytes[10..12].try_into().unwrap()),
+        }
+    }
+}
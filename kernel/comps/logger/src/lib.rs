@@ -2,9 +2,13 @@
 
 //! The logger implementation for Asterinas.
 //!
-//! This logger now has the most basic logging functionality, controls the output
-//! based on the globally set log level. Different log levels will be represented
-//! with different colors if enabling `log_color` feature.
+//! This logger controls the output based on the globally set log level, which can be
+//! overridden for some modules or files at runtime (see [`dynamic_debug`]). Different log
+//! levels will be represented with different colors if enabling `log_color` feature.
+//!
+//! Every log record is also stored in the kernel message ring buffer (see [`kmsg`]), from which
+//! user space can read the records via `syslog(2)` and `/dev/kmsg`. Only the records whose
+//! levels are below the console log level (see [`console_level`]) are printed.
 //!
 //! This logger guarantees _atomicity_ under concurrency: messages are always
 //! printed in their entirety without being mixed with messages generated
@@ -20,8 +24,18 @@ use component::{ComponentInitError, init_component};
 
 mod aster_logger;
 mod console;
+pub mod console_level;
+pub mod dynamic_debug;
+pub mod kmsg;
+mod ratelimit;
 
 pub use console::_print;
+#[doc(hidden)]
+pub use log as __log;
+pub use ratelimit::{
+    RateLimit, ratelimit_burst, ratelimit_interval_secs, set_ratelimit_burst,
+    set_ratelimit_interval_secs,
+};
 
 #[init_component]
 fn init() -> Result<(), ComponentInitError> {
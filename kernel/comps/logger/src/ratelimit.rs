@@ -0,0 +1,151 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Rate limiting of log floods.
+//!
+//! A [`RateLimit`] allows at most `kernel.printk_ratelimit_burst` messages in every
+//! `kernel.printk_ratelimit` seconds. Suppressed messages are counted and reported once the
+//! next interval begins.
+
+use core::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+
+use ostd::{
+    sync::{LocalIrqDisabled, SpinLock},
+    timer::Jiffies,
+};
+
+/// The length of a rate-limiting interval in seconds.
+static RATELIMIT_INTERVAL_SECS: AtomicU32 = AtomicU32::new(5);
+/// The maximum number of messages in a rate-limiting interval.
+static RATELIMIT_BURST: AtomicU32 = AtomicU32::new(10);
+
+/// Returns the length of a rate-limiting interval in seconds (`kernel.printk_ratelimit`).
+pub fn ratelimit_interval_secs() -> u32 {
+    RATELIMIT_INTERVAL_SECS.load(Ordering::Relaxed)
+}
+
+/// Sets the length of a rate-limiting interval in seconds.
+///
+/// An interval of zero disables rate limiting.
+pub fn set_ratelimit_interval_secs(secs: u32) {
+    RATELIMIT_INTERVAL_SECS.store(secs, Ordering::Relaxed);
+}
+
+/// Returns the maximum number of messages in an interval (`kernel.printk_ratelimit_burst`).
+pub fn ratelimit_burst() -> u32 {
+    RATELIMIT_BURST.load(Ordering::Relaxed)
+}
+
+/// Sets the maximum number of messages in an interval.
+pub fn set_ratelimit_burst(burst: u32) {
+    RATELIMIT_BURST.store(burst, Ordering::Relaxed);
+}
+
+/// The state of rate limiting for a source of messages.
+pub struct RateLimit {
+    state: SpinLock<RateLimitState, LocalIrqDisabled>,
+}
+
+struct RateLimitState {
+    /// The time when the current interval began.
+    begin: Option<Duration>,
+    /// The number of messages allowed in the current interval.
+    printed: u32,
+    /// The number of messages suppressed in the current interval.
+    missed: u32,
+}
+
+impl RateLimit {
+    /// Creates a new rate limit.
+    pub const fn new() -> Self {
+        Self {
+            state: SpinLock::new(RateLimitState {
+                begin: None,
+                printed: 0,
+                missed: 0,
+            }),
+        }
+    }
+
+    /// Returns whether a message may be logged now.
+    ///
+    /// If some messages have been suppressed in the previous interval, a warning with `name` is
+    /// logged to report them.
+    pub fn check(&self, name: &str) -> bool {
+        let interval_secs = ratelimit_interval_secs();
+        if interval_secs == 0 {
+            return true;
+        }
+
+        let now = Jiffies::elapsed().as_duration();
+        let mut state = self.state.lock();
+
+        let begin = *state.begin.get_or_insert(now);
+        let mut missed = 0;
+        if now - begin >= Duration::from_secs(interval_secs as u64) {
+            missed = core::mem::take(&mut state.missed);
+            state.begin = Some(now);
+            state.printed = 0;
+        }
+
+        let is_allowed = state.printed < ratelimit_burst();
+        if is_allowed {
+            state.printed += 1;
+        } else {
+            state.missed += 1;
+        }
+        drop(state);
+
+        // Log after releasing the lock, since the message may go through another rate limit.
+        if missed > 0 {
+            log::warn!("{}: {} callbacks suppressed", name, missed);
+        }
+
+        is_allowed
+    }
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Logs a message with the given level unless messages from the call site are flooding.
+///
+/// The arguments are the same as [`log::log!`] with a level.
+#[macro_export]
+macro_rules! log_ratelimited {
+    ($level:expr, $($arg:tt)+) => {{
+        static RATELIMIT: $crate::RateLimit = $crate::RateLimit::new();
+        if $crate::__log::log_enabled!($level) && RATELIMIT.check(module_path!()) {
+            $crate::__log::log!($level, $($arg)+);
+        }
+    }};
+}
+
+/// Logs an error message unless messages from the call site are flooding.
+#[macro_export]
+macro_rules! error_ratelimited {
+    ($($arg:tt)+) => {
+        $crate::log_ratelimited!($crate::__log::Level::Error, $($arg)+)
+    };
+}
+
+/// Logs a warning message unless messages from the call site are flooding.
+#[macro_export]
+macro_rules! warn_ratelimited {
+    ($($arg:tt)+) => {
+        $crate::log_ratelimited!($crate::__log::Level::Warn, $($arg)+)
+    };
+}
+
+/// Logs an informational message unless messages from the call site are flooding.
+#[macro_export]
+macro_rules! info_ratelimited {
+    ($($arg:tt)+) => {
+        $crate::log_ratelimited!($crate::__log::Level::Info, $($arg)+)
+    };
+}
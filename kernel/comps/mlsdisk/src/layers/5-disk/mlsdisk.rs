@@ -162,6 +162,8 @@ impl<D: BlockSet + 'static> aster_block::BlockDevice for MlsDisk<D> {
         BlockDeviceMeta {
             max_nr_segments_per_bio: usize::MAX,
             nr_sectors: (BLOCK_SIZE / SECTOR_SIZE) * self.total_blocks(),
+            supports_fua: false,
+            max_discard_sectors: 0,
         }
     }
 
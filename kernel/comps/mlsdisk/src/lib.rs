@@ -193,6 +193,8 @@ mod test {
             BlockDeviceMeta {
                 max_nr_segments_per_bio: usize::MAX,
                 nr_sectors: self.blocks.size() / SECTOR_SIZE,
+                supports_fua: false,
+                max_discard_sectors: 0,
             }
         }
 
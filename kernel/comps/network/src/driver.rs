@@ -65,7 +65,7 @@ impl device::TxToken for TxToken<'_> {
     {
         let mut buffer = vec![0u8; len];
         let res = f(&mut buffer);
-        self.0.send(&buffer).expect("Send packet failed");
+        self.0.send(&mut buffer).expect("Send packet failed");
         res
     }
 }
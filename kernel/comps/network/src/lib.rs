@@ -53,7 +53,9 @@ pub trait AnyNetworkDevice: Send + Sync + Any + Debug {
     fn receive(&mut self) -> Result<RxBuffer, NetError>;
 
     /// Sends a packet to network.
-    fn send(&mut self, packet: &[u8]) -> Result<(), NetError>;
+    ///
+    /// The driver may modify the packet in place (e.g., to prepare it for checksum offloading).
+    fn send(&mut self, packet: &mut [u8]) -> Result<(), NetError>;
 
     /// Frees processes tx buffers.
     fn free_processed_tx_buffers(&mut self);
@@ -0,0 +1,212 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The NVMe commands and their completions.
+//!
+//! Reference: NVM Express Base Specification, Revision 2.0, Section 4.
+
+/// The size of a submission queue entry, as a power of two.
+pub(crate) const SQ_ENTRY_SIZE_SHIFT: u32 = 6;
+/// The size of a completion queue entry, as a power of two.
+pub(crate) const CQ_ENTRY_SIZE_SHIFT: u32 = 4;
+
+/// The opcodes of the admin commands.
+pub(crate) mod admin_opcode {
+    pub(crate) const CREATE_IO_SQ: u8 = 0x01;
+    pub(crate) const CREATE_IO_CQ: u8 = 0x05;
+    pub(crate) const IDENTIFY: u8 = 0x06;
+    pub(crate) const SET_FEATURES: u8 = 0x09;
+}
+
+/// The opcodes of the NVM I/O commands.
+pub(crate) mod io_opcode {
+    pub(crate) const FLUSH: u8 = 0x00;
+    pub(crate) const WRITE: u8 = 0x01;
+    pub(crate) const READ: u8 = 0x02;
+    pub(crate) const DATASET_MANAGEMENT: u8 = 0x09;
+}
+
+/// The Controller or Namespace Structure (CNS) values of the Identify command.
+pub(crate) mod identify_cns {
+    pub(crate) const NAMESPACE: u32 = 0x00;
+    pub(crate) const CONTROLLER: u32 = 0x01;
+    pub(crate) const ACTIVE_NAMESPACE_LIST: u32 = 0x02;
+}
+
+/// The feature identifier of the Number of Queues feature.
+const FEATURE_NUMBER_OF_QUEUES: u32 = 0x07;
+
+/// A submission queue entry.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(crate) struct SubmissionEntry {
+    /// The opcode in bits 7:0 and the command identifier in bits 31:16.
+    cdw0: u32,
+    nsid: u32,
+    cdw2: u32,
+    cdw3: u32,
+    mptr: u64,
+    prp1: u64,
+    prp2: u64,
+    cdw10: u32,
+    cdw11: u32,
+    cdw12: u32,
+    cdw13: u32,
+    cdw14: u32,
+    cdw15: u32,
+}
+
+impl SubmissionEntry {
+    fn new(opcode: u8, nsid: u32) -> Self {
+        Self {
+            cdw0: opcode as u32,
+            nsid,
+            ..Default::default()
+        }
+    }
+
+    /// Creates an Identify command, which fills the data structure at `prp1`.
+    pub(crate) fn identify(cns: u32, nsid: u32, prp1: u64) -> Self {
+        Self {
+            prp1,
+            cdw10: cns,
+            ..Self::new(admin_opcode::IDENTIFY, nsid)
+        }
+    }
+
+    /// Creates a Set Features command that requests the numbers of the I/O queues.
+    ///
+    /// The numbers do not include the admin queues.
+    pub(crate) fn set_number_of_queues(nr_sqs: u16, nr_cqs: u16) -> Self {
+        Self {
+            cdw10: FEATURE_NUMBER_OF_QUEUES,
+            cdw11: (nr_sqs as u32 - 1) | ((nr_cqs as u32 - 1) << 16),
+            ..Self::new(admin_opcode::SET_FEATURES, 0)
+        }
+    }
+
+    /// Creates a Create I/O Completion Queue command.
+    ///
+    /// The queue is physically contiguous and raises interrupts at the MSI-X vector.
+    pub(crate) fn create_io_cq(qid: u16, depth: u16, prp1: u64, vector: u16) -> Self {
+        const PC: u32 = 1 << 0;
+        const IEN: u32 = 1 << 1;
+
+        Self {
+            prp1,
+            cdw10: qid as u32 | ((depth as u32 - 1) << 16),
+            cdw11: PC | IEN | ((vector as u32) << 16),
+            ..Self::new(admin_opcode::CREATE_IO_CQ, 0)
+        }
+    }
+
+    /// Creates a Create I/O Submission Queue command.
+    ///
+    /// The queue is physically contiguous and posts the completions to the queue of `cqid`.
+    pub(crate) fn create_io_sq(qid: u16, depth: u16, prp1: u64, cqid: u16) -> Self {
+        const PC: u32 = 1 << 0;
+
+        Self {
+            prp1,
+            cdw10: qid as u32 | ((depth as u32 - 1) << 16),
+            cdw11: PC | ((cqid as u32) << 16),
+            ..Self::new(admin_opcode::CREATE_IO_SQ, 0)
+        }
+    }
+
+    /// Creates a Read or Write command for `nr_blocks` logical blocks starting at `lba`.
+    pub(crate) fn read_write(
+        opcode: u8,
+        nsid: u32,
+        lba: u64,
+        nr_blocks: u16,
+        prp1: u64,
+        prp2: u64,
+    ) -> Self {
+        debug_assert!(opcode == io_opcode::READ || opcode == io_opcode::WRITE);
+        debug_assert!(nr_blocks > 0);
+
+        Self {
+            prp1,
+            prp2,
+            cdw10: lba as u32,
+            cdw11: (lba >> 32) as u32,
+            cdw12: nr_blocks as u32 - 1,
+            ..Self::new(opcode, nsid)
+        }
+    }
+
+    /// Sets the Force Unit Access bit of a Read or Write command.
+    ///
+    /// The command completes only after the data are persistent.
+    pub(crate) fn set_fua(&mut self) {
+        const FUA: u32 = 1 << 30;
+
+        self.cdw12 |= FUA;
+    }
+
+    /// Creates a Flush command.
+    pub(crate) fn flush(nsid: u32) -> Self {
+        Self::new(io_opcode::FLUSH, nsid)
+    }
+
+    /// Creates a Dataset Management command that deallocates the ranges at `prp1`.
+    ///
+    /// The ranges must not cross a page boundary.
+    pub(crate) fn deallocate(nsid: u32, nr_ranges: u32, prp1: u64) -> Self {
+        const AD: u32 = 1 << 2;
+
+        debug_assert!(nr_ranges > 0);
+
+        Self {
+            prp1,
+            cdw10: nr_ranges - 1,
+            cdw11: AD,
+            ..Self::new(io_opcode::DATASET_MANAGEMENT, nsid)
+        }
+    }
+
+    /// Sets the command identifier.
+    pub(crate) fn set_cid(&mut self, cid: u16) {
+        self.cdw0 = (self.cdw0 & 0xFFFF) | ((cid as u32) << 16);
+    }
+}
+
+/// A range of the Dataset Management command.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(crate) struct DatasetRange {
+    pub(crate) attributes: u32,
+    pub(crate) nr_blocks: u32,
+    pub(crate) lba: u64,
+}
+
+/// A completion queue entry.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(crate) struct CompletionEntry {
+    /// The command-specific result.
+    pub(crate) result: u32,
+    reserved: u32,
+    sq_head: u16,
+    sq_id: u16,
+    pub(crate) cid: u16,
+    /// The phase tag in bit 0 and the status in bits 15:1.
+    status: u16,
+}
+
+impl CompletionEntry {
+    /// The offset of the `status` field.
+    pub(crate) const STATUS_OFFSET: usize = 14;
+
+    /// Returns the phase tag of the status field.
+    pub(crate) fn phase(status: u16) -> bool {
+        status & 1 != 0
+    }
+
+    /// Returns the status of the command, excluding the phase tag.
+    ///
+    /// Zero means that the command has completed successfully.
+    pub(crate) fn status(&self) -> u16 {
+        self.status >> 1
+    }
+}
@@ -0,0 +1,354 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{string::String, sync::Arc, vec, vec::Vec};
+use core::{hint::spin_loop, time::Duration};
+
+use aster_pci::{
+    capability::{
+        CapabilityData,
+        msix::{CapabilityMsixData, IrqAffinity},
+    },
+    cfg_space::{Bar, Command},
+    common_device::PciCommonDevice,
+};
+use log::{info, warn};
+use ostd::{
+    arch::trap::TrapFrame,
+    cpu::num_cpus,
+    io::IoMem,
+    irq::IrqLine,
+    mm::{HasDaddr, PAGE_SIZE, VmIo, VmIoOnce, dma::DmaCoherent},
+    timer::Jiffies,
+};
+
+use crate::{
+    command::{CQ_ENTRY_SIZE_SHIFT, SQ_ENTRY_SIZE_SHIFT, SubmissionEntry, identify_cns},
+    io_queue::IoQueue,
+    queue::QueuePair,
+};
+
+/// The offsets of the controller registers.
+mod reg {
+    /// Controller Capabilities.
+    pub(super) const CAP: usize = 0x00;
+    /// Version.
+    pub(super) const VS: usize = 0x08;
+    /// Controller Configuration.
+    pub(super) const CC: usize = 0x14;
+    /// Controller Status.
+    pub(super) const CSTS: usize = 0x1C;
+    /// Admin Queue Attributes.
+    pub(super) const AQA: usize = 0x24;
+    /// Admin Submission Queue Base Address.
+    pub(super) const ASQ: usize = 0x28;
+    /// Admin Completion Queue Base Address.
+    pub(super) const ACQ: usize = 0x30;
+}
+
+/// The Enable bit of the Controller Configuration register.
+const CC_EN: u32 = 1 << 0;
+/// The Ready bit of the Controller Status register.
+const CSTS_RDY: u32 = 1 << 0;
+/// The Controller Fatal Status bit of the Controller Status register.
+const CSTS_CFS: u32 = 1 << 1;
+
+const ADMIN_QUEUE_DEPTH: u16 = 32;
+const IO_QUEUE_DEPTH: u16 = 64;
+/// The maximum number of I/O queue pairs for each controller.
+const MAX_IO_QUEUES: usize = 8;
+/// The time before an admin command is considered lost.
+const ADMIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// An error that occurs when initializing an NVMe controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NvmeError {
+    /// The registers are not mapped by a memory BAR.
+    NoRegisters,
+    /// The controller does not support MSI-X.
+    NoMsix,
+    /// The controller does not support the page size of the kernel.
+    UnsupportedPageSize,
+    /// The controller does not respond in time.
+    Timeout,
+    /// The controller reports a fatal error.
+    ControllerFatal,
+    /// An admin command fails with the status.
+    CommandFailed(u16),
+}
+
+/// The information of an active namespace.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct NamespaceInfo {
+    pub(crate) nsid: u32,
+    /// The size of the namespace in logical blocks.
+    pub(crate) nr_blocks: u64,
+    /// The size of a logical block, as a power of two.
+    pub(crate) lba_shift: u32,
+}
+
+/// An NVMe controller.
+#[derive(Debug)]
+pub(crate) struct NvmeController {
+    index: u32,
+    /// The admin queue pair, which is only used when initializing the controller.
+    ///
+    /// It is kept here since the controller may still access it.
+    _admin_queue: QueuePair,
+    io_queues: Vec<Arc<IoQueue>>,
+    has_volatile_write_cache: bool,
+    supports_deallocate: bool,
+    /// The MSI-X capability, which keeps the interrupt handlers of the I/O queues registered.
+    _msix: CapabilityMsixData,
+}
+
+impl NvmeController {
+    /// Resets and initializes the controller, returning the active namespaces.
+    pub(crate) fn init(
+        device: &PciCommonDevice,
+        index: u32,
+    ) -> Result<(Arc<Self>, Vec<NamespaceInfo>), NvmeError> {
+        let Some(Bar::Memory(bar)) = device.bar_manager().bar(0) else {
+            return Err(NvmeError::NoRegisters);
+        };
+        let regs = bar.io_mem().clone();
+        device.write_command(device.read_command() | Command::MEMORY_SPACE | Command::BUS_MASTER);
+
+        let mut msix = device
+            .capabilities()
+            .iter()
+            .find_map(|cap| match cap.capability_data() {
+                CapabilityData::Msix(msix) => Some(msix.clone()),
+                _ => None,
+            })
+            .ok_or(NvmeError::NoMsix)?;
+
+        let cap: u64 = regs.read_once(reg::CAP).unwrap();
+        let max_queue_depth = ((cap & 0xFFFF) + 1).min(u16::MAX as u64) as u16;
+        let ready_timeout = Duration::from_millis(((cap >> 24) & 0xFF) * 500);
+        let doorbell_stride = 4 << ((cap >> 32) & 0xF);
+        let min_page_size = 1 << (12 + ((cap >> 48) & 0xF));
+        let max_page_size = 1 << (12 + ((cap >> 52) & 0xF));
+        if !(min_page_size..=max_page_size).contains(&(PAGE_SIZE as u64)) {
+            return Err(NvmeError::UnsupportedPageSize);
+        }
+
+        // Disable the controller before setting up the admin queues.
+        let cc: u32 = regs.read_once(reg::CC).unwrap();
+        if cc & CC_EN != 0 {
+            regs.write_once(reg::CC, &(cc & !CC_EN)).unwrap();
+        }
+        wait_for_ready(&regs, false, ready_timeout)?;
+
+        let admin_depth = ADMIN_QUEUE_DEPTH.min(max_queue_depth);
+        let admin_queue = QueuePair::new(0, admin_depth, regs.clone(), doorbell_stride);
+        let aqa = (admin_depth as u32 - 1) | ((admin_depth as u32 - 1) << 16);
+        regs.write_once(reg::AQA, &aqa).unwrap();
+        regs.write_once(reg::ASQ, &admin_queue.sq_daddr()).unwrap();
+        regs.write_once(reg::ACQ, &admin_queue.cq_daddr()).unwrap();
+
+        // Select the NVM command set and the memory page size of the kernel.
+        let mps = PAGE_SIZE.ilog2() - 12;
+        let cc = CC_EN | (mps << 7) | (SQ_ENTRY_SIZE_SHIFT << 16) | (CQ_ENTRY_SIZE_SHIFT << 20);
+        regs.write_once(reg::CC, &cc).unwrap();
+        wait_for_ready(&regs, true, ready_timeout)?;
+
+        let buf = DmaCoherent::alloc(1, true).unwrap();
+        let buf_daddr = buf.daddr() as u64;
+
+        execute_admin(
+            &admin_queue,
+            SubmissionEntry::identify(identify_cns::CONTROLLER, 0, buf_daddr),
+        )?;
+        let mut model = [0u8; 40];
+        buf.read_bytes(24, &mut model).unwrap();
+        let mdts: u8 = buf.read_val(77).unwrap();
+        let nr_namespaces: u32 = buf.read_val(516).unwrap();
+        let oncs: u16 = buf.read_val(520).unwrap();
+        let vwc: u8 = buf.read_val(525).unwrap();
+        let version: u32 = regs.read_once(reg::VS).unwrap();
+        info!(
+            "NVMe controller {}: {}, version {}.{}",
+            index,
+            String::from_utf8_lossy(&model).trim_end(),
+            version >> 16,
+            (version >> 8) & 0xFF
+        );
+
+        // The maximum data transfer size is in units of the minimum page size.
+        let max_pages_per_command = if mdts == 0 {
+            usize::MAX
+        } else {
+            (min_page_size as usize) << mdts >> PAGE_SIZE.ilog2()
+        };
+
+        // Use one interrupt vector for each I/O queue, and leave vector 0 to the admin queue. If
+        // there is only one vector, it is shared.
+        let nr_vectors = msix.table_size() as usize;
+        let nr_wanted = num_cpus()
+            .min(MAX_IO_QUEUES)
+            .min(nr_vectors.saturating_sub(1).max(1));
+        let result = execute_admin(
+            &admin_queue,
+            SubmissionEntry::set_number_of_queues(nr_wanted as u16, nr_wanted as u16),
+        )?;
+        let nr_io_queues = nr_wanted
+            .min((result & 0xFFFF) as usize + 1)
+            .min((result >> 16) as usize + 1);
+
+        // Spread the vectors of the I/O queues across CPUs.
+        if nr_vectors > 1 {
+            msix.alloc_irq_vectors(
+                nr_io_queues as u16 + 1,
+                &IrqAffinity {
+                    pre_vectors: 1,
+                    post_vectors: 0,
+                },
+            );
+        }
+
+        let io_depth = IO_QUEUE_DEPTH.min(max_queue_depth);
+        let mut io_queues = Vec::with_capacity(nr_io_queues);
+        for qid in 1..=nr_io_queues as u16 {
+            let vector = if nr_vectors > 1 { qid } else { 0 };
+            let pair = QueuePair::new(qid, io_depth, regs.clone(), doorbell_stride);
+            execute_admin(
+                &admin_queue,
+                SubmissionEntry::create_io_cq(qid, io_depth, pair.cq_daddr(), vector),
+            )?;
+            execute_admin(
+                &admin_queue,
+                SubmissionEntry::create_io_sq(qid, io_depth, pair.sq_daddr(), qid),
+            )?;
+            let io_queue = Arc::new(IoQueue::new(pair, max_pages_per_command));
+
+            if msix.irq_mut(vector as usize).is_none() {
+                msix.set_interrupt_vector(IrqLine::alloc().unwrap(), vector);
+            }
+            let cloned_queue = io_queue.clone();
+            msix.irq_mut(vector as usize)
+                .unwrap()
+                .on_active(move |_: &TrapFrame| cloned_queue.handle_irq());
+
+            io_queues.push(io_queue);
+        }
+
+        // The active namespace list is only supported since NVMe 1.1.
+        let nsids = if version >= 0x0001_0100 {
+            execute_admin(
+                &admin_queue,
+                SubmissionEntry::identify(identify_cns::ACTIVE_NAMESPACE_LIST, 0, buf_daddr),
+            )?;
+            let mut nsids = vec![0u32; PAGE_SIZE / size_of::<u32>()];
+            buf.read_slice(0, &mut nsids).unwrap();
+            nsids.into_iter().take_while(|nsid| *nsid != 0).collect()
+        } else {
+            (1..=nr_namespaces).collect::<Vec<_>>()
+        };
+
+        let mut namespaces = Vec::new();
+        for nsid in nsids {
+            execute_admin(
+                &admin_queue,
+                SubmissionEntry::identify(identify_cns::NAMESPACE, nsid, buf_daddr),
+            )?;
+            let nr_blocks: u64 = buf.read_val(0).unwrap();
+            let flbas: u8 = buf.read_val(26).unwrap();
+            let lba_format: u32 = buf.read_val(128 + 4 * (flbas & 0xF) as usize).unwrap();
+            let metadata_size = lba_format & 0xFFFF;
+            let lba_shift = (lba_format >> 16) & 0xFF;
+
+            // An inactive namespace is identified as all zeros.
+            if nr_blocks == 0 {
+                continue;
+            }
+            if metadata_size != 0 || !(9..=PAGE_SIZE.ilog2()).contains(&lba_shift) {
+                warn!(
+                    "NVMe namespace {} has an unsupported format {:#x}",
+                    nsid, lba_format
+                );
+                continue;
+            }
+
+            namespaces.push(NamespaceInfo {
+                nsid,
+                nr_blocks,
+                lba_shift,
+            });
+        }
+
+        let controller = Arc::new(Self {
+            index,
+            _admin_queue: admin_queue,
+            io_queues,
+            has_volatile_write_cache: vwc & 1 != 0,
+            // The Dataset Management command is supported.
+            supports_deallocate: oncs & (1 << 2) != 0,
+            _msix: msix,
+        });
+        Ok((controller, namespaces))
+    }
+
+    /// Returns the index of the controller, which is used in the device names.
+    pub(crate) fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the I/O queue pairs.
+    pub(crate) fn io_queues(&self) -> &[Arc<IoQueue>] {
+        &self.io_queues
+    }
+
+    /// Returns whether the controller has a volatile write cache that needs flushing.
+    pub(crate) fn has_volatile_write_cache(&self) -> bool {
+        self.has_volatile_write_cache
+    }
+
+    /// Returns whether the logical blocks can be deallocated, i.e., discarded.
+    pub(crate) fn supports_deallocate(&self) -> bool {
+        self.supports_deallocate
+    }
+}
+
+/// Waits until the Ready bit of the controller becomes `ready`.
+fn wait_for_ready(regs: &IoMem, ready: bool, timeout: Duration) -> Result<(), NvmeError> {
+    let deadline = Jiffies::elapsed().as_duration() + timeout;
+
+    loop {
+        let csts: u32 = regs.read_once(reg::CSTS).unwrap();
+        if ready && csts & CSTS_CFS != 0 {
+            return Err(NvmeError::ControllerFatal);
+        }
+        if (csts & CSTS_RDY != 0) == ready {
+            return Ok(());
+        }
+        if Jiffies::elapsed().as_duration() > deadline {
+            return Err(NvmeError::Timeout);
+        }
+        spin_loop();
+    }
+}
+
+/// Executes an admin command and polls for its completion, returning the command-specific result.
+///
+/// The admin commands are executed one at a time, so they all use the same command identifier.
+fn execute_admin(admin_queue: &QueuePair, mut entry: SubmissionEntry) -> Result<u32, NvmeError> {
+    entry.set_cid(0);
+    admin_queue.submit(&entry);
+
+    let deadline = Jiffies::elapsed().as_duration() + ADMIN_TIMEOUT;
+    loop {
+        let mut completion = None;
+        admin_queue.pop_completions(|entry| completion = Some(entry));
+
+        if let Some(entry) = completion {
+            return match entry.status() {
+                0 => Ok(entry.result),
+                status => Err(NvmeError::CommandFailed(status)),
+            };
+        }
+        if Jiffies::elapsed().as_duration() > deadline {
+            return Err(NvmeError::Timeout);
+        }
+        spin_loop();
+    }
+}
@@ -0,0 +1,69 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{collections::vec_deque::VecDeque, sync::Arc};
+
+use aster_pci::{
+    PCI_BUS, PciDeviceId,
+    bus::{PciDevice, PciDriver},
+    common_device::PciCommonDevice,
+};
+use ostd::{bus::BusProbeError, sync::SpinLock};
+use spin::Once;
+
+pub(crate) static NVME_PCI_DRIVER: Once<Arc<NvmePciDriver>> = Once::new();
+
+pub(crate) fn init() {
+    NVME_PCI_DRIVER.call_once(|| Arc::new(NvmePciDriver::new()));
+    PCI_BUS
+        .lock()
+        .register_driver(NVME_PCI_DRIVER.get().unwrap().clone());
+}
+
+/// The PCI driver that claims the NVMe controllers.
+///
+/// The claimed controllers are initialized later by the component.
+#[derive(Debug)]
+pub(crate) struct NvmePciDriver {
+    devices: SpinLock<VecDeque<PciCommonDevice>>,
+}
+
+impl NvmePciDriver {
+    fn new() -> Self {
+        Self {
+            devices: SpinLock::new(VecDeque::new()),
+        }
+    }
+
+    pub(crate) fn pop_device(&self) -> Option<PciCommonDevice> {
+        self.devices.lock().pop_front()
+    }
+}
+
+impl PciDriver for NvmePciDriver {
+    fn probe(
+        &self,
+        device: PciCommonDevice,
+    ) -> Result<Arc<dyn PciDevice>, (BusProbeError, PciCommonDevice)> {
+        // Mass storage controller, Non-Volatile memory controller, NVM Express
+        const NVME_CLASS: (u8, u8, u8) = (0x01, 0x08, 0x02);
+
+        let device_id = *device.device_id();
+        if (device_id.class, device_id.subclass, device_id.prog_if) != NVME_CLASS {
+            return Err((BusProbeError::DeviceNotMatch, device));
+        }
+        self.devices.lock().push_back(device);
+
+        Ok(Arc::new(NvmePciDevice { device_id }))
+    }
+}
+
+#[derive(Debug)]
+struct NvmePciDevice {
+    device_id: PciDeviceId,
+}
+
+impl PciDevice for NvmePciDevice {
+    fn device_id(&self) -> PciDeviceId {
+        self.device_id
+    }
+}
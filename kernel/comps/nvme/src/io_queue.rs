@@ -0,0 +1,310 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{sync::Arc, vec, vec::Vec};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use aster_block::{
+    SECTOR_SIZE,
+    bio::{BioFlags, BioStatus, BioType},
+    request_queue::BioRequest,
+};
+use id_alloc::IdAlloc;
+use log::warn;
+use ostd::{
+    mm::{HasDaddr, HasSize, PAGE_SIZE, VmIo, dma::DmaCoherent},
+    sync::{SpinLock, WaitQueue},
+};
+
+use crate::{
+    command::{DatasetRange, SubmissionEntry, io_opcode},
+    queue::QueuePair,
+};
+
+/// The maximum number of pages that a command can transfer.
+///
+/// Each command has at most one PRP list, which fits in a page. The first page is described by
+/// the command itself, so one more page could fit, but the limit is kept as a power of two.
+pub(crate) const MAX_PAGES_PER_COMMAND: usize = PAGE_SIZE / size_of::<u64>();
+
+/// An I/O queue pair, to which the requests of all the namespaces can be submitted.
+#[derive(Debug)]
+pub(crate) struct IoQueue {
+    pair: QueuePair,
+    /// The allocator of the command identifiers.
+    cids: SpinLock<IdAlloc>,
+    /// The waiters for free command identifiers.
+    wait_queue: WaitQueue,
+    /// The requests of the outstanding commands, indexed by the command identifiers.
+    inflight: SpinLock<Vec<Option<Arc<InflightRequest>>>>,
+    /// The PRP lists, one page for each command identifier.
+    ///
+    /// The page also holds the ranges of a Dataset Management command.
+    prp_lists: DmaCoherent,
+    max_pages_per_command: usize,
+}
+
+/// A request that may be split into multiple commands.
+#[derive(Debug)]
+struct InflightRequest {
+    request: BioRequest,
+    nr_pending_commands: AtomicUsize,
+    has_failed: AtomicBool,
+}
+
+impl InflightRequest {
+    fn complete(&self) {
+        let status = if self.has_failed.load(Ordering::Relaxed) {
+            BioStatus::IoError
+        } else {
+            BioStatus::Complete
+        };
+
+        // Synchronize DMA mapping if read from the device
+        if status == BioStatus::Complete && self.request.type_() == BioType::Read {
+            self.request
+                .bios()
+                .flat_map(|bio| bio.segments().iter())
+                .for_each(|segment| segment.inner_dma_slice().sync_from_device().unwrap());
+        }
+
+        self.request.bios().for_each(|bio| bio.complete(status));
+    }
+}
+
+/// A command that transfers data.
+#[derive(Debug)]
+struct Transfer {
+    /// The device addresses of the pieces of the buffer.
+    ///
+    /// Only the first piece can start at an unaligned address, and only the last piece can end
+    /// at an unaligned address.
+    prps: Vec<u64>,
+    nr_bytes: usize,
+    /// The device address of the end of the last piece.
+    end: u64,
+}
+
+impl IoQueue {
+    /// Creates an I/O queue for the queue pair.
+    ///
+    /// The queue pair must have been created in the controller.
+    pub(crate) fn new(pair: QueuePair, max_pages_per_command: usize) -> Self {
+        // Up to `depth - 1` commands can be outstanding.
+        let nr_cids = pair.depth() as usize - 1;
+        let prp_lists = DmaCoherent::alloc(nr_cids, true).unwrap();
+
+        Self {
+            pair,
+            cids: SpinLock::new(IdAlloc::with_capacity(nr_cids)),
+            wait_queue: WaitQueue::new(),
+            inflight: SpinLock::new(vec![None; nr_cids]),
+            prp_lists,
+            max_pages_per_command: max_pages_per_command.min(MAX_PAGES_PER_COMMAND),
+        }
+    }
+
+    /// Submits a read or write request to the namespace.
+    ///
+    /// The request is split into multiple commands if it is too big or its segments cannot be
+    /// described by a single PRP list. This method will wait if the queue is full.
+    pub(crate) fn submit_read_write(&self, nsid: u32, lba_shift: u32, request: BioRequest) {
+        let opcode = match request.type_() {
+            BioType::Read => io_opcode::READ,
+            BioType::Write => io_opcode::WRITE,
+            _ => unreachable!(),
+        };
+        let is_fua = request.flags().contains(BioFlags::FUA);
+
+        let sector_shift = lba_shift - SECTOR_SIZE.ilog2();
+        let start_sector = request.sid_range().start.to_raw();
+        let transfers = if start_sector & ((1 << sector_shift) - 1) == 0 {
+            self.split_into_transfers(&request, lba_shift)
+        } else {
+            None
+        };
+        let Some(transfers) = transfers else {
+            warn!("unaligned NVMe request: {:?}", request);
+            request.bios().for_each(|bio| bio.complete(BioStatus::IoError));
+            return;
+        };
+
+        let inflight = Arc::new(InflightRequest {
+            request,
+            nr_pending_commands: AtomicUsize::new(transfers.len()),
+            has_failed: AtomicBool::new(false),
+        });
+
+        let mut lba = start_sector >> sector_shift;
+        for transfer in transfers {
+            let nr_blocks = transfer.nr_bytes >> lba_shift;
+            let cid = self.alloc_cid();
+
+            let prp2 = match transfer.prps.len() {
+                1 => 0,
+                2 => transfer.prps[1],
+                _ => {
+                    let offset = cid as usize * PAGE_SIZE;
+                    self.prp_lists
+                        .write_slice(offset, &transfer.prps[1..])
+                        .unwrap();
+                    (self.prp_lists.daddr() + offset) as u64
+                }
+            };
+            let mut entry = SubmissionEntry::read_write(
+                opcode,
+                nsid,
+                lba,
+                nr_blocks as u16,
+                transfer.prps[0],
+                prp2,
+            );
+            if is_fua {
+                entry.set_fua();
+            }
+            self.submit(cid, entry, inflight.clone());
+
+            lba += nr_blocks as u64;
+        }
+    }
+
+    /// Submits a flush request to the namespace.
+    pub(crate) fn submit_flush(&self, nsid: u32, request: BioRequest) {
+        let inflight = Arc::new(InflightRequest {
+            request,
+            nr_pending_commands: AtomicUsize::new(1),
+            has_failed: AtomicBool::new(false),
+        });
+
+        let cid = self.alloc_cid();
+        self.submit(cid, SubmissionEntry::flush(nsid), inflight);
+    }
+
+    /// Submits a discard request to the namespace, which deallocates the logical blocks.
+    pub(crate) fn submit_discard(&self, nsid: u32, lba_shift: u32, request: BioRequest) {
+        let sector_shift = lba_shift - SECTOR_SIZE.ilog2();
+        let start_sector = request.sid_range().start.to_raw();
+        let nr_sectors = request.num_sectors() as u64;
+        let nr_blocks = nr_sectors >> sector_shift;
+        if (start_sector | nr_sectors) & ((1 << sector_shift) - 1) != 0
+            || nr_blocks == 0
+            || nr_blocks > u32::MAX as u64
+        {
+            warn!("unaligned or oversized NVMe discard request: {:?}", request);
+            request.bios().for_each(|bio| bio.complete(BioStatus::IoError));
+            return;
+        }
+
+        let inflight = Arc::new(InflightRequest {
+            request,
+            nr_pending_commands: AtomicUsize::new(1),
+            has_failed: AtomicBool::new(false),
+        });
+
+        let cid = self.alloc_cid();
+        let range = DatasetRange {
+            attributes: 0,
+            nr_blocks: nr_blocks as u32,
+            lba: start_sector >> sector_shift,
+        };
+        let offset = cid as usize * PAGE_SIZE;
+        self.prp_lists.write_val(offset, &range).unwrap();
+        let prp1 = (self.prp_lists.daddr() + offset) as u64;
+        self.submit(cid, SubmissionEntry::deallocate(nsid, 1, prp1), inflight);
+    }
+
+    /// Handles the interrupt raised for the completion queue.
+    pub(crate) fn handle_irq(&self) {
+        let mut finished = Vec::new();
+
+        self.pair.pop_completions(|entry| {
+            let cid = entry.cid as usize;
+            let Some(inflight) = self.inflight.lock()[cid].take() else {
+                warn!("NVMe completion for an unknown command {}", cid);
+                return;
+            };
+            self.cids.lock().free(cid);
+
+            if entry.status() != 0 {
+                warn!("NVMe I/O command failed with status {:#x}", entry.status());
+                inflight.has_failed.store(true, Ordering::Relaxed);
+            }
+            if inflight.nr_pending_commands.fetch_sub(1, Ordering::Relaxed) == 1 {
+                finished.push(inflight);
+            }
+        });
+
+        self.wait_queue.wake_all();
+        finished.iter().for_each(|inflight| inflight.complete());
+    }
+
+    fn alloc_cid(&self) -> u16 {
+        self.wait_queue
+            .wait_until(|| self.cids.disable_irq().lock().alloc())
+            .try_into()
+            .unwrap()
+    }
+
+    fn submit(&self, cid: u16, mut entry: SubmissionEntry, inflight: Arc<InflightRequest>) {
+        entry.set_cid(cid);
+        self.inflight.disable_irq().lock()[cid as usize] = Some(inflight);
+        self.pair.submit(&entry);
+    }
+
+    /// Splits the segments of the request into transfers.
+    ///
+    /// This method returns `None` if a transfer would not be a multiple of the logical block
+    /// size.
+    fn split_into_transfers(
+        &self,
+        request: &BioRequest,
+        lba_shift: u32,
+    ) -> Option<Vec<Transfer>> {
+        let page_mask = PAGE_SIZE as u64 - 1;
+        let mut transfers: Vec<Transfer> = Vec::new();
+
+        let dma_slices = request
+            .bios()
+            .flat_map(|bio| bio.segments().iter())
+            .map(|segment| segment.inner_dma_slice());
+        for dma_slice in dma_slices {
+            let mut addr = dma_slice.daddr() as u64;
+            let end = addr + dma_slice.size() as u64;
+
+            while addr < end {
+                let piece_end = end.min((addr & !page_mask) + PAGE_SIZE as u64);
+
+                let can_append = transfers.last().is_some_and(|transfer| {
+                    transfer.end & page_mask == 0
+                        && addr & page_mask == 0
+                        && transfer.prps.len() < self.max_pages_per_command
+                });
+                if can_append {
+                    let transfer = transfers.last_mut().unwrap();
+                    transfer.prps.push(addr);
+                    transfer.nr_bytes += (piece_end - addr) as usize;
+                    transfer.end = piece_end;
+                } else {
+                    if let Some(transfer) = transfers.last()
+                        && transfer.nr_bytes & ((1 << lba_shift) - 1) != 0
+                    {
+                        return None;
+                    }
+                    transfers.push(Transfer {
+                        prps: vec![addr],
+                        nr_bytes: (piece_end - addr) as usize,
+                        end: piece_end,
+                    });
+                }
+
+                addr = piece_end;
+            }
+        }
+
+        if let Some(transfer) = transfers.last()
+            && transfer.nr_bytes & ((1 << lba_shift) - 1) != 0
+        {
+            return None;
+        }
+        Some(transfers)
+    }
+}
@@ -0,0 +1,57 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The NVMe driver of Asterinas.
+//!
+//! The driver claims the NVMe controllers on the PCI bus. Each controller has an admin queue
+//! pair, which is used to initialize the controller, and multiple I/O queue pairs, each of which
+//! raises interrupts on its own MSI-X vector. Each active namespace of a controller is registered
+//! as a block device named `nvme<controller>n<namespace>`.
+//!
+//! Reference: NVM Express Base Specification, Revision 2.0.
+
+#![no_std]
+#![deny(unsafe_code)]
+
+extern crate alloc;
+#[macro_use]
+extern crate ostd_pod;
+
+mod command;
+mod controller;
+mod driver;
+mod io_queue;
+mod namespace;
+mod queue;
+
+use component::{ComponentInitError, init_component};
+use log::error;
+pub use namespace::NvmeNamespace;
+
+use self::{controller::NvmeController, driver::NVME_PCI_DRIVER};
+
+#[init_component]
+fn nvme_init() -> Result<(), ComponentInitError> {
+    driver::init();
+
+    let mut index = 0;
+    while let Some(device) = NVME_PCI_DRIVER.get().unwrap().pop_device() {
+        let (controller, namespaces) = match NvmeController::init(&device, index) {
+            Ok(res) => res,
+            Err(err) => {
+                error!(
+                    "failed to initialize the NVMe controller at {:?}: {:?}",
+                    device.location(),
+                    err
+                );
+                continue;
+            }
+        };
+        index += 1;
+
+        for info in namespaces {
+            NvmeNamespace::init(controller.clone(), info);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,169 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{
+    format,
+    string::String,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+
+use aster_block::{
+    BlockDevice, BlockDeviceMeta, EXTENDED_DEVICE_ID_ALLOCATOR, PartitionInfo, PartitionNode,
+    SECTOR_SIZE,
+    bio::{BioEnqueueError, BioStatus, BioType, SubmittedBio, bio_segment_pool_init},
+    request_queue::{BioRequest, BioRequestQueue},
+};
+use device_id::DeviceId;
+use ostd::sync::SpinLock;
+
+use crate::controller::{NamespaceInfo, NvmeController};
+
+/// An NVMe namespace, which is exposed as a disk.
+///
+/// All the namespaces of a controller share the I/O queues of the controller. The requests of a
+/// namespace should be handled by one thread for each I/O queue (see
+/// [`NvmeNamespace::handle_requests`]).
+#[derive(Debug)]
+pub struct NvmeNamespace {
+    controller: Arc<NvmeController>,
+    info: NamespaceInfo,
+    queue: BioRequestQueue,
+    id: DeviceId,
+    name: String,
+    partitions: SpinLock<Option<Vec<Arc<PartitionNode>>>>,
+    weak_self: Weak<Self>,
+}
+
+impl NvmeNamespace {
+    /// Creates a namespace of the controller and registers it.
+    pub(crate) fn init(controller: Arc<NvmeController>, info: NamespaceInfo) {
+        // Like Linux, the NVMe disks and their partitions all use the extended device IDs.
+        let id = EXTENDED_DEVICE_ID_ALLOCATOR.get().unwrap().allocate();
+        let name = format!("nvme{}n{}", controller.index(), info.nsid);
+
+        let namespace = Arc::new_cyclic(|weak_self| Self {
+            controller,
+            info,
+            queue: BioRequestQueue::new(),
+            id,
+            name,
+            partitions: SpinLock::new(None),
+            weak_self: weak_self.clone(),
+        });
+        // The controller reorders the requests by itself.
+        namespace.queue.set_scheduler("none").unwrap();
+
+        aster_block::register(namespace).unwrap();
+
+        bio_segment_pool_init();
+    }
+
+    /// Returns the number of the I/O queues.
+    pub fn num_io_queues(&self) -> usize {
+        self.controller.io_queues().len()
+    }
+
+    /// Dequeues a `BioRequest` from the software staging queue and
+    /// submits the request to the I/O queue of `queue_index`.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `queue_index` is not less than [`Self::num_io_queues`].
+    pub fn handle_requests(&self, queue_index: usize) {
+        let io_queue = &self.controller.io_queues()[queue_index];
+        let request = self.queue.dequeue();
+
+        match request.type_() {
+            BioType::Read | BioType::Write => {
+                io_queue.submit_read_write(self.info.nsid, self.info.lba_shift, request)
+            }
+            BioType::Flush if self.controller.has_volatile_write_cache() => {
+                io_queue.submit_flush(self.info.nsid, request)
+            }
+            BioType::Flush => complete_request(&request, BioStatus::Complete),
+            BioType::Discard if self.controller.supports_deallocate() => {
+                io_queue.submit_discard(self.info.nsid, self.info.lba_shift, request)
+            }
+            BioType::Discard => complete_request(&request, BioStatus::NotSupported),
+        }
+    }
+}
+
+fn complete_request(request: &BioRequest, status: BioStatus) {
+    request.bios().for_each(|bio| bio.complete(status));
+}
+
+impl BlockDevice for NvmeNamespace {
+    fn enqueue(&self, bio: SubmittedBio) -> Result<(), BioEnqueueError> {
+        self.queue.enqueue(bio)
+    }
+
+    fn metadata(&self) -> BlockDeviceMeta {
+        BlockDeviceMeta {
+            max_nr_segments_per_bio: self.queue.max_nr_segments_per_bio(),
+            nr_sectors: (self.info.nr_blocks << self.info.lba_shift) as usize / SECTOR_SIZE,
+            supports_fua: true,
+            max_discard_sectors: if self.controller.supports_deallocate() {
+                // A range of the Dataset Management command has at most `u32::MAX` logical
+                // blocks.
+                (u32::MAX as usize) << (self.info.lba_shift - SECTOR_SIZE.ilog2())
+            } else {
+                0
+            },
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn id(&self) -> DeviceId {
+        self.id
+    }
+
+    fn set_partitions(&self, infos: Vec<Option<PartitionInfo>>) {
+        let old_partitions = self.partitions.lock().take();
+        for partition in old_partitions.into_iter().flatten() {
+            let _ = aster_block::unregister(partition.id());
+            EXTENDED_DEVICE_ID_ALLOCATOR
+                .get()
+                .unwrap()
+                .release(partition.id());
+        }
+
+        let mut new_partitions = Vec::new();
+        for (index, info_opt) in infos.iter().enumerate() {
+            let Some(info) = info_opt else {
+                continue;
+            };
+
+            let number = index as u32 + 1;
+            let id = EXTENDED_DEVICE_ID_ALLOCATOR.get().unwrap().allocate();
+            let name = aster_block::partition_name(self.name(), number);
+            let device = self.weak_self.upgrade().unwrap();
+
+            let partition = Arc::new(PartitionNode::new(id, name, device, number, *info));
+            new_partitions.push(partition);
+        }
+
+        for partition in new_partitions.iter() {
+            let _ = aster_block::register(partition.clone());
+        }
+
+        *self.partitions.lock() = Some(new_partitions);
+    }
+
+    fn partitions(&self) -> Option<Vec<Arc<dyn BlockDevice>>> {
+        let partitions = self.partitions.lock();
+        let devices = partitions
+            .as_ref()?
+            .iter()
+            .map(|p| p.clone() as Arc<dyn BlockDevice>)
+            .collect();
+        Some(devices)
+    }
+
+    fn request_queue(&self) -> Option<&BioRequestQueue> {
+        Some(&self.queue)
+    }
+}
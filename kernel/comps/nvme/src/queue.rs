@@ -0,0 +1,129 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use ostd::{
+    io::IoMem,
+    mm::{HasDaddr, PAGE_SIZE, VmIo, VmIoOnce, dma::DmaCoherent},
+    sync::SpinLock,
+};
+
+use crate::command::{CQ_ENTRY_SIZE_SHIFT, CompletionEntry, SQ_ENTRY_SIZE_SHIFT, SubmissionEntry};
+
+/// A pair of a submission queue and a completion queue.
+///
+/// The completions of the commands submitted to the submission queue are posted to the
+/// completion queue. The caller must make sure that no more than `depth - 1` commands are
+/// outstanding at a time, so that neither queue can overflow.
+#[derive(Debug)]
+pub(crate) struct QueuePair {
+    depth: u16,
+    sq: DmaCoherent,
+    cq: DmaCoherent,
+    /// The register space of the controller, where the doorbells are.
+    regs: IoMem,
+    sq_doorbell: usize,
+    cq_doorbell: usize,
+    sq_tail: SpinLock<u16>,
+    cq_state: SpinLock<CqState>,
+}
+
+#[derive(Debug)]
+struct CqState {
+    head: u16,
+    /// The phase tag of the entries that are newly posted by the controller.
+    ///
+    /// The controller inverts the phase tag each time it wraps around the queue.
+    phase: bool,
+}
+
+impl QueuePair {
+    /// Allocates a queue pair of `id` with `depth` entries.
+    ///
+    /// The doorbells are located by the doorbell stride of the controller.
+    pub(crate) fn new(id: u16, depth: u16, regs: IoMem, doorbell_stride: usize) -> Self {
+        let sq_size = (depth as usize) << SQ_ENTRY_SIZE_SHIFT;
+        let cq_size = (depth as usize) << CQ_ENTRY_SIZE_SHIFT;
+        let sq = DmaCoherent::alloc(sq_size.div_ceil(PAGE_SIZE), true).unwrap();
+        let cq = DmaCoherent::alloc(cq_size.div_ceil(PAGE_SIZE), true).unwrap();
+
+        const DOORBELL_BASE: usize = 0x1000;
+        let sq_doorbell = DOORBELL_BASE + (2 * id as usize) * doorbell_stride;
+        let cq_doorbell = DOORBELL_BASE + (2 * id as usize + 1) * doorbell_stride;
+
+        Self {
+            depth,
+            sq,
+            cq,
+            regs,
+            sq_doorbell,
+            cq_doorbell,
+            sq_tail: SpinLock::new(0),
+            cq_state: SpinLock::new(CqState {
+                head: 0,
+                phase: true,
+            }),
+        }
+    }
+
+    /// Returns the number of entries in each queue.
+    pub(crate) fn depth(&self) -> u16 {
+        self.depth
+    }
+
+    /// Returns the device address of the submission queue.
+    pub(crate) fn sq_daddr(&self) -> u64 {
+        self.sq.daddr() as u64
+    }
+
+    /// Returns the device address of the completion queue.
+    pub(crate) fn cq_daddr(&self) -> u64 {
+        self.cq.daddr() as u64
+    }
+
+    /// Submits a command and notifies the controller.
+    pub(crate) fn submit(&self, entry: &SubmissionEntry) {
+        let mut tail = self.sq_tail.disable_irq().lock();
+
+        self.sq
+            .write_val((*tail as usize) << SQ_ENTRY_SIZE_SHIFT, entry)
+            .unwrap();
+        *tail = (*tail + 1) % self.depth;
+        self.regs
+            .write_once(self.sq_doorbell, &(*tail as u32))
+            .unwrap();
+    }
+
+    /// Pops all the newly posted completions.
+    ///
+    /// The controller is notified that the entries are consumed after `f` is called on them.
+    pub(crate) fn pop_completions(&self, mut f: impl FnMut(CompletionEntry)) {
+        let mut state = self.cq_state.disable_irq().lock();
+        let mut has_popped = false;
+
+        loop {
+            let offset = (state.head as usize) << CQ_ENTRY_SIZE_SHIFT;
+            let status: u16 = self
+                .cq
+                .read_once(offset + CompletionEntry::STATUS_OFFSET)
+                .unwrap();
+            if CompletionEntry::phase(status) != state.phase {
+                break;
+            }
+
+            let entry: CompletionEntry = self.cq.read_val(offset).unwrap();
+            f(entry);
+            has_popped = true;
+
+            state.head += 1;
+            if state.head == self.depth {
+                state.head = 0;
+                state.phase = !state.phase;
+            }
+        }
+
+        if has_popped {
+            self.regs
+                .write_once(self.cq_doorbell, &(state.head as u32))
+                .unwrap();
+        }
+    }
+}
@@ -111,6 +111,11 @@ pub(crate) fn init() -> Option<RangeInclusive<u8>> {
 
 pub(crate) const MSIX_DEFAULT_MSG_ADDR: u32 = 0x2400_0000;
 
+pub(crate) fn construct_msix_address(_destination_id: u32) -> u32 {
+    // The destination is not encoded in the message address.
+    MSIX_DEFAULT_MSG_ADDR
+}
+
 pub(crate) fn construct_remappable_msix_address(_remapping_index: u32) -> u32 {
     unimplemented!()
 }
@@ -6,10 +6,11 @@ use alloc::vec::Vec;
 
 use align_ext::AlignExt;
 
-use self::{msix::CapabilityMsixData, vendor::CapabilityVndrData};
+use self::{msi::CapabilityMsiData, msix::CapabilityMsixData, vendor::CapabilityVndrData};
 use super::{cfg_space::Status, common_device::PciCommonDevice};
 use crate::cfg_space::PciGeneralDeviceCfgOffset;
 
+pub mod msi;
 pub mod msix;
 pub mod vendor;
 
@@ -31,7 +32,7 @@ pub enum CapabilityData {
     /// Id:0x04, Slot Identification
     SlotId,
     /// Id:0x05, Message Signalled Interrupts
-    Msi,
+    Msi(CapabilityMsiData),
     /// Id:0x06, CompactPCI HotSwap
     Chswp,
     /// Id:0x07, PCI-X
@@ -110,7 +111,7 @@ impl Capability {
                 0x02 => CapabilityData::Agp,
                 0x03 => CapabilityData::Vpd,
                 0x04 => CapabilityData::SlotId,
-                0x05 => CapabilityData::Msi,
+                0x05 => CapabilityData::Msi(CapabilityMsiData::new(dev, cap_ptr)),
                 0x06 => CapabilityData::Chswp,
                 0x07 => CapabilityData::PciX,
                 0x08 => CapabilityData::Hp,
@@ -0,0 +1,117 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! MSI capability support.
+
+use alloc::boxed::Box;
+
+use ostd::irq::IrqLine;
+
+use crate::{
+    PciDeviceLocation,
+    arch::{construct_msix_address, construct_remappable_msix_address},
+    cfg_space::{Command, PciCommonCfgOffset},
+    common_device::PciCommonDevice,
+};
+
+/// MSI capability.
+///
+/// Only one vector is used, even if the device is capable of multiple messages. Unlike MSI-X, MSI
+/// is not enabled until an interrupt line is set via [`Self::set_interrupt_vector`].
+#[derive(Debug, Clone)]
+pub struct CapabilityMsiData {
+    loc: PciDeviceLocation,
+    ptr: u16,
+    is_64bit: bool,
+    irq: Option<IrqLine>,
+}
+
+impl CapabilityMsiData {
+    /// The MSI Enable bit of the Message Control register.
+    const ENABLE: u16 = 1 << 0;
+    /// The Multiple Message Enable bits of the Message Control register.
+    const MULTIPLE_MESSAGE_ENABLE: u16 = 0b111 << 4;
+    /// The 64-bit Address Capable bit of the Message Control register.
+    const ADDRESS_64BIT: u16 = 1 << 7;
+
+    pub(super) fn new(dev: &PciCommonDevice, cap_ptr: u16) -> Self {
+        let msg_ctrl = dev.location().read16(cap_ptr + 2);
+
+        // Disable MSI until an interrupt line is set.
+        dev.location().write16(
+            cap_ptr + 2,
+            msg_ctrl & !(Self::ENABLE | Self::MULTIPLE_MESSAGE_ENABLE),
+        );
+
+        Self {
+            loc: *dev.location(),
+            ptr: cap_ptr,
+            is_64bit: msg_ctrl & Self::ADDRESS_64BIT != 0,
+            irq: None,
+        }
+    }
+
+    /// Enables MSI with the interrupt line.
+    ///
+    /// If an interrupt line has already been set, the old [`IrqLine`] will be replaced.
+    ///
+    /// The interrupts are delivered to the CPU specified by the affinity of the interrupt line,
+    /// which can be changed later via [`IrqLine::set_affinity`].
+    pub fn set_interrupt_vector(&mut self, irq: IrqLine) {
+        let data_offset = if self.is_64bit { 12 } else { 8 };
+
+        if let Some(old_irq) = self.irq.take() {
+            old_irq.set_affinity_notifier(None);
+        }
+
+        // If interrupt remapping is enabled, then the message address must be changed.
+        let (address, data) = if let Some(remapping_index) = irq.remapping_index() {
+            (construct_remappable_msix_address(remapping_index as u32), 0)
+        } else {
+            // Otherwise, the destination is encoded in the message address, which must be
+            // updated when the affinity is changed.
+            let loc = self.loc;
+            let address_offset = self.ptr + 4;
+            irq.set_affinity_notifier(Some(Box::new(move |destination_id| {
+                loc.write32(address_offset, construct_msix_address(destination_id));
+            })));
+
+            (
+                construct_msix_address(irq.destination_id()),
+                irq.num() as u16,
+            )
+        };
+        self.loc.write32(self.ptr + 4, address);
+        if self.is_64bit {
+            self.loc.write32(self.ptr + 8, 0);
+        }
+        self.loc.write16(self.ptr + data_offset, data);
+
+        self.irq = Some(irq);
+
+        // Enable MSI with a single message.
+        let msg_ctrl = self.loc.read16(self.ptr + 2);
+        self.loc.write16(
+            self.ptr + 2,
+            (msg_ctrl & !Self::MULTIPLE_MESSAGE_ENABLE) | Self::ENABLE,
+        );
+        // Disable INTx.
+        let command = self.loc.read16(PciCommonCfgOffset::Command as u16);
+        self.loc.write16(
+            PciCommonCfgOffset::Command as u16,
+            command | Command::INTERRUPT_DISABLE.bits(),
+        );
+    }
+
+    /// Returns a mutable reference to the [`IrqLine`].
+    ///
+    /// Users can register callbacks using the returned [`IrqLine`] reference.
+    pub fn irq_mut(&mut self) -> Option<&mut IrqLine> {
+        self.irq.as_mut()
+    }
+
+    /// Returns true if the MSI Enable bit is set.
+    pub fn is_enabled(&self) -> bool {
+        let msg_ctrl = self.loc.read16(self.ptr + 2);
+        msg_ctrl & Self::ENABLE != 0
+    }
+}
@@ -2,13 +2,18 @@
 
 //! MSI-X capability support.
 
-use alloc::{sync::Arc, vec::Vec};
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
 
-use ostd::{irq::IrqLine, mm::VmIoOnce};
+use log::warn;
+use ostd::{
+    cpu::{CpuId, num_cpus},
+    irq::IrqLine,
+    mm::VmIoOnce,
+};
 
 use crate::{
     PciDeviceLocation,
-    arch::{MSIX_DEFAULT_MSG_ADDR, construct_remappable_msix_address},
+    arch::{MSIX_DEFAULT_MSG_ADDR, construct_msix_address, construct_remappable_msix_address},
     cfg_space::{Bar, Command, MemoryBar},
     common_device::PciCommonDevice,
 };
@@ -135,41 +140,112 @@ impl CapabilityMsixData {
     /// Enables an interrupt line.
     ///
     /// If the interrupt line has already been enabled, the old [`IrqLine`] will be replaced.
+    ///
+    /// The interrupts are delivered to the CPU specified by the affinity of the interrupt line,
+    /// which can be changed later via [`IrqLine::set_affinity`].
     pub fn set_interrupt_vector(&mut self, irq: IrqLine, index: u16) {
         if index >= self.table_size {
             return;
         }
 
+        if let Some(old_irq) = self.irqs[index as usize].take() {
+            old_irq.set_affinity_notifier(None);
+        }
+
+        let entry_offset = (16 * index) as usize + self.table_offset;
+
         // If interrupt remapping is enabled, then we need to change the value of the message address.
         if let Some(remapping_index) = irq.remapping_index() {
             let address = construct_remappable_msix_address(remapping_index as u32);
 
             self.table_bar
                 .io_mem()
-                .write_once((16 * index) as usize + self.table_offset, &address)
+                .write_once(entry_offset, &address)
                 .unwrap();
             self.table_bar
                 .io_mem()
-                .write_once((16 * index + 8) as usize + self.table_offset, &0)
+                .write_once(entry_offset + 8, &0)
                 .unwrap();
         } else {
+            // Otherwise, the destination is encoded in the message address, which must be
+            // updated when the affinity is changed.
+            let table_bar = self.table_bar.clone();
+            irq.set_affinity_notifier(Some(Box::new(move |destination_id| {
+                let io_mem = table_bar.io_mem();
+                // Mask the vector while the entry is being modified.
+                let vector_control: u32 = io_mem.read_once(entry_offset + 12).unwrap();
+                io_mem
+                    .write_once(entry_offset + 12, &(vector_control | 1))
+                    .unwrap();
+                io_mem
+                    .write_once(entry_offset, &construct_msix_address(destination_id))
+                    .unwrap();
+                io_mem
+                    .write_once(entry_offset + 12, &vector_control)
+                    .unwrap();
+            })));
+
             self.table_bar
                 .io_mem()
-                .write_once(
-                    (16 * index + 8) as usize + self.table_offset,
-                    &(irq.num() as u32),
-                )
+                .write_once(entry_offset, &construct_msix_address(irq.destination_id()))
+                .unwrap();
+            self.table_bar
+                .io_mem()
+                .write_once(entry_offset + 8, &(irq.num() as u32))
                 .unwrap();
         }
 
-        let _old_irq = self.irqs[index as usize].replace(irq);
+        self.irqs[index as usize] = Some(irq);
         // Enable this MSI-X vector.
         self.table_bar
             .io_mem()
-            .write_once((16 * index + 12) as usize + self.table_offset, &0_u32)
+            .write_once(entry_offset + 12, &0_u32)
             .unwrap();
     }
 
+    /// Allocates and enables the interrupt lines for the first `num_vectors` vectors, returning
+    /// the number of the enabled vectors.
+    ///
+    /// The vectors that are not excluded by `affinity` are spread across the CPUs, e.g., so that
+    /// each queue of a multi-queue device interrupts a different CPU. Their affinities are
+    /// managed, i.e., they cannot be changed by the users. The other vectors are delivered to the
+    /// BSP by default.
+    ///
+    /// Fewer vectors are enabled if the MSI-X Table is too small or the interrupt lines run out.
+    /// The vectors that have already been enabled are kept.
+    pub fn alloc_irq_vectors(&mut self, num_vectors: u16, affinity: &IrqAffinity) -> u16 {
+        let num_vectors = num_vectors.min(self.table_size);
+        let managed_start = affinity.pre_vectors.min(num_vectors);
+        let managed_end = num_vectors
+            .saturating_sub(affinity.post_vectors)
+            .max(managed_start);
+        let num_managed = (managed_end - managed_start) as usize;
+
+        for index in 0..num_vectors {
+            if self.irqs[index as usize].is_none() {
+                let Ok(irq) = IrqLine::alloc() else {
+                    return index;
+                };
+                self.set_interrupt_vector(irq, index);
+            }
+
+            if !(managed_start..managed_end).contains(&index) {
+                continue;
+            }
+            let irq = self.irqs[index as usize].as_ref().unwrap();
+            let cpu_id = spread_cpu((index - managed_start) as usize, num_managed);
+            if let Err(err) = irq.set_affinity(cpu_id) {
+                warn!(
+                    "MSI-X vector {} of {:?}: failed to set affinity to {:?}: {:?}",
+                    index, self.loc, cpu_id, err
+                );
+            }
+            irq.set_affinity_managed(true);
+        }
+
+        num_vectors
+    }
+
     /// Returns a mutable reference to the [`IrqLine`].
     ///
     /// Users can register callbacks using the returned [`IrqLine`] reference.
@@ -183,3 +259,27 @@ impl CapabilityMsixData {
         msg_ctrl & 0x8000 != 0
     }
 }
+
+/// The affinity requirements of the vectors allocated by
+/// [`CapabilityMsixData::alloc_irq_vectors`].
+///
+/// The vectors at the beginning and at the end, e.g., for the configuration changes or the
+/// admin queue, are not spread across the CPUs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IrqAffinity {
+    /// The number of the vectors at the beginning that are not spread.
+    pub pre_vectors: u16,
+    /// The number of the vectors at the end that are not spread.
+    pub post_vectors: u16,
+}
+
+/// Returns the CPU that the `index`-th of the `num` spread vectors is delivered to.
+fn spread_cpu(index: usize, num: usize) -> CpuId {
+    let num_cpus = num_cpus();
+    let cpu_index = if num <= num_cpus {
+        index * num_cpus / num
+    } else {
+        index % num_cpus
+    };
+    CpuId::try_from(cpu_index).unwrap()
+}
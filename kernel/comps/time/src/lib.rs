@@ -13,6 +13,7 @@ use core::time::Duration;
 pub use clocksource::{ClockSource, Instant};
 use component::{ComponentInitError, init_component};
 use rtc::Driver;
+pub use rtc::RtcError;
 use spin::Once;
 
 mod clocksource;
@@ -21,12 +22,20 @@ mod tsc;
 
 pub static VDSO_DATA_HIGH_RES_UPDATE_FN: Once<fn(Instant, u64)> = Once::new();
 
+/// The function that is called when the alarm of the RTC fires.
+///
+/// The function is called in the interrupt context.
+pub static RTC_ALARM_HANDLER_FN: Once<fn()> = Once::new();
+
 static RTC_DRIVER: Once<Arc<dyn Driver + Send + Sync>> = Once::new();
 
 #[init_component]
 fn time_init() -> Result<(), ComponentInitError> {
-    let rtc = rtc::init_rtc_driver();
-    RTC_DRIVER.call_once(|| rtc);
+    if let Some(rtc) = rtc::init_rtc_driver() {
+        RTC_DRIVER.call_once(|| rtc);
+    } else {
+        log::warn!("No RTC device found, falling back to the Unix epoch");
+    }
     tsc::init();
     Ok(())
 }
@@ -42,6 +51,19 @@ pub struct SystemTime {
     pub nanos: u64,
 }
 
+impl SystemTime {
+    /// The Unix epoch, which represents 1970-01-01 00:00:00.
+    pub const UNIX_EPOCH: Self = Self {
+        year: 1970,
+        month: 1,
+        day: 1,
+        hour: 0,
+        minute: 0,
+        second: 0,
+        nanos: 0,
+    };
+}
+
 static START_TIME: Once<SystemTime> = Once::new();
 
 /// Returns the `START_TIME`, which is the system time when calibrating.
@@ -49,6 +71,31 @@ pub fn read_start_time() -> SystemTime {
     *START_TIME.get().unwrap()
 }
 
+/// Returns whether there is an RTC device.
+pub fn has_rtc() -> bool {
+    RTC_DRIVER.get().is_some()
+}
+
+/// Reads the time from the RTC.
+pub fn read_rtc() -> Result<SystemTime, RtcError> {
+    let rtc = RTC_DRIVER.get().ok_or(RtcError::NoDevice)?;
+    Ok(rtc.read_rtc())
+}
+
+/// Sets the time of the RTC.
+pub fn set_rtc(time: &SystemTime) -> Result<(), RtcError> {
+    let rtc = RTC_DRIVER.get().ok_or(RtcError::NoDevice)?;
+    rtc.set_rtc(time)
+}
+
+/// Sets the alarm of the RTC, or disables the alarm if `time` is `None`.
+///
+/// The alarm fires only once, after which [`RTC_ALARM_HANDLER_FN`] is called.
+pub fn set_rtc_alarm(time: Option<&SystemTime>) -> Result<(), RtcError> {
+    let rtc = RTC_DRIVER.get().ok_or(RtcError::NoDevice)?;
+    rtc.set_alarm(time)
+}
+
 /// Returns the monotonic time from the TSC clocksource.
 pub fn read_monotonic_time() -> Duration {
     let instant = tsc::read_instant();
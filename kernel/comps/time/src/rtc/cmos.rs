@@ -13,19 +13,34 @@
 //! <https://elixir.bootlin.com/linux/v6.17.5/source/arch/x86/kernel/rtc.c#L69>
 //! <https://www.scs.stanford.edu/23wi-cs212/pintos/specs/mc146818a.pdf>
 
+use alloc::sync::Arc;
 use core::num::NonZeroU8;
 
 use log::warn;
-use ostd::{arch::{device::io_port::{ReadWriteAccess, WriteOnlyAccess}, kernel::ACPI_INFO}, io::IoPort, sync::SpinLock};
+use ostd::{
+    arch::{
+        device::io_port::{ReadWriteAccess, WriteOnlyAccess},
+        irq::{IRQ_CHIP, MappedIrqLine},
+        kernel::ACPI_INFO,
+    },
+    io::IoPort,
+    irq::IrqLine,
+    sync::{LocalIrqDisabled, SpinLock},
+};
 
 use crate::SystemTime;
-use super::Driver;
+use super::{Driver, RtcError};
 
 pub struct RtcCmos {
-    access: SpinLock<CmosAccess>,
+    access: Arc<SpinLock<CmosAccess, LocalIrqDisabled>>,
     status_b: StatusB,
+    /// The IRQ line of the alarm interrupt, if available.
+    irq_line: Option<MappedIrqLine>,
 }
 
+/// The ISA interrupt number of the CMOS RTC.
+const ISA_INTR_NUM: u8 = 8;
+
 impl Driver for RtcCmos {
     fn try_new() -> Option<Self> {
         // TODO: Due to historical reasons, the "NMI Enable" bit (named `NMI_EN` in Intel's
@@ -74,15 +89,92 @@ impl Driver for RtcCmos {
             return None;
         }
 
+        let access = Arc::new(SpinLock::new(access));
+
+        let irq_line = match IrqLine::alloc().and_then(|irq_line| {
+            IRQ_CHIP
+                .get()
+                .unwrap()
+                .map_isa_pin_to(irq_line, ISA_INTR_NUM)
+        }) {
+            Ok(mut irq_line) => {
+                let access = access.clone();
+                irq_line.on_active(move |_| handle_irq(&access));
+                Some(irq_line)
+            }
+            Err(_) => {
+                warn!("CMOS RTC IRQ line is not available, the alarm is disabled");
+                None
+            }
+        };
+
         Some(Self {
-            access: SpinLock::new(access),
+            access,
             status_b,
+            irq_line,
         })
     }
 
     fn read_rtc(&self) -> SystemTime {
         CmosData::read_rtc(self).into()
     }
+
+    fn set_rtc(&self, time: &SystemTime) -> Result<(), RtcError> {
+        let data = CmosData::from_system_time(time, self)?;
+        data.write_rtc(self);
+        Ok(())
+    }
+
+    /// Sets the alarm of the CMOS RTC.
+    ///
+    /// The alarm registers only contain the hour, the minute, and the second. Therefore, the alarm
+    /// fires at the first match, which may be earlier than the specified time if the time is more
+    /// than one day later.
+    fn set_alarm(&self, time: Option<&SystemTime>) -> Result<(), RtcError> {
+        if self.irq_line.is_none() {
+            return Err(RtcError::Unsupported);
+        }
+
+        let alarm = time
+            .map(|time| CmosData::from_system_time(time, self))
+            .transpose()?;
+
+        let mut access = self.access.lock();
+
+        let status_b = access.read_status_b();
+        access.write_status_b(status_b - StatusB::AIE);
+        // Clear the pending alarm, if any.
+        access.read_status_c();
+
+        let Some(alarm) = alarm else {
+            return Ok(());
+        };
+
+        access.write_register(Register::SecondAlarm, alarm.second);
+        access.write_register(Register::MinuteAlarm, alarm.minute);
+        access.write_register(Register::HourAlarm, alarm.hour);
+        access.write_status_b(status_b | StatusB::AIE);
+
+        Ok(())
+    }
+}
+
+/// Handles the interrupts of the CMOS RTC.
+fn handle_irq(access: &SpinLock<CmosAccess, LocalIrqDisabled>) {
+    let mut access = access.lock();
+
+    // Reading the register C acknowledges the interrupt.
+    let status_c = access.read_status_c();
+    let status_b = access.read_status_b();
+    if !status_c.contains(StatusC::AF) || !status_b.contains(StatusB::AIE) {
+        return;
+    }
+
+    // The alarm fires only once.
+    access.write_status_b(status_b - StatusB::AIE);
+    drop(access);
+
+    super::notify_alarm();
 }
 
 struct CmosAccess {
@@ -94,15 +186,18 @@ struct CmosAccess {
 #[repr(u8)]
 enum Register {
     Second = 0x00,
+    SecondAlarm = 0x01,
     Minute = 0x02,
+    MinuteAlarm = 0x03,
     Hour = 0x04,
+    HourAlarm = 0x05,
     Day = 0x07,
     Month = 0x08,
     Year = 0x09,
 
     StatusA = 0x0A,
     StatusB = 0x0B,
-    // `StatusC` is not used.
+    StatusC = 0x0C,
     StatusD = 0x0D,
 }
 
@@ -115,6 +210,18 @@ bitflags::bitflags! {
 
 bitflags::bitflags! {
     struct StatusB: u8 {
+        /// The SET bit.
+        ///
+        /// This bit is set to stop the RTC updates while the time is being set.
+        const SET = 1 << 7;
+        /// The periodic interrupt enable (PIE) bit.
+        const PIE = 1 << 6;
+        /// The alarm interrupt enable (AIE) bit.
+        const AIE = 1 << 5;
+        /// The update-ended interrupt enable (UIE) bit.
+        const UIE = 1 << 4;
+        /// The square-wave enable (SQWE) bit.
+        const SQWE = 1 << 3;
         /// The data mode (DM) bit.
         ///
         /// This bit is set when the binary format is used; otherwise, the BCD format is used.
@@ -123,6 +230,21 @@ bitflags::bitflags! {
         ///
         /// This bit is set when the 24-hour format is used; otherwise, the 12-hour format is used.
         const CM_24HOUR = 1 << 1;
+        /// The daylight savings enable (DSE) bit.
+        const DSE = 1 << 0;
+    }
+}
+
+bitflags::bitflags! {
+    struct StatusC: u8 {
+        /// The interrupt request flag (IRQF).
+        const IRQF = 1 << 7;
+        /// The periodic interrupt flag (PF).
+        const PF = 1 << 6;
+        /// The alarm interrupt flag (AF).
+        const AF = 1 << 5;
+        /// The update-ended interrupt flag (UF).
+        const UF = 1 << 4;
     }
 }
 
@@ -152,6 +274,24 @@ impl CmosAccess {
         StatusB::from_bits_truncate(self.read_register_impl(Register::StatusB as u8))
     }
 
+    pub(self) fn read_status_c(&mut self) -> StatusC {
+        StatusC::from_bits_truncate(self.read_register_impl(Register::StatusC as u8))
+    }
+
+    pub(self) fn write_register(&mut self, reg: Register, val: u8) {
+        self.write_register_impl(reg as u8, val);
+    }
+
+    pub(self) fn write_century(&mut self, val: u8) {
+        if let Some(r) = self.century_register {
+            self.write_register_impl(r.get(), val);
+        }
+    }
+
+    pub(self) fn write_status_b(&mut self, status_b: StatusB) {
+        self.write_register_impl(Register::StatusB as u8, status_b.bits());
+    }
+
     pub(self) fn check_presence(&mut self) -> bool {
         // If a working CMOS RTC is present, `VRT` should be set and all other reserved bits should
         // not be set.
@@ -162,6 +302,11 @@ impl CmosAccess {
         self.io_sel.write(reg);
         self.io_val.read()
     }
+
+    fn write_register_impl(&mut self, reg: u8, val: u8) {
+        self.io_sel.write(reg);
+        self.io_val.write(val);
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -199,6 +344,58 @@ impl CmosData {
         now
     }
 
+    pub(self) fn from_system_time(time: &SystemTime, rtc: &RtcCmos) -> Result<Self, RtcError> {
+        const DEFAULT_21_CENTURY: u16 = 20;
+
+        let century = time.year / 100;
+        // Without the century register, only the years in the 21st century can be represented.
+        if rtc.access.lock().century_register.is_none() && century != DEFAULT_21_CENTURY {
+            return Err(RtcError::InvalidTime);
+        }
+        if !(1..=99).contains(&century) {
+            return Err(RtcError::InvalidTime);
+        }
+
+        let mut data = CmosData {
+            century: NonZeroU8::new(century as u8),
+            year: time.year % 100,
+            month: time.month,
+            day: time.day,
+            hour: time.hour,
+            minute: time.minute,
+            second: time.second,
+        };
+
+        if !rtc.status_b.contains(StatusB::CM_24HOUR) {
+            data.convert_24_hour_to_12_hour();
+        }
+        if !rtc.status_b.contains(StatusB::DM_BINARY) {
+            data.convert_binary_to_bcd();
+        }
+
+        Ok(data)
+    }
+
+    pub(self) fn write_rtc(&self, rtc: &RtcCmos) {
+        let mut access = rtc.access.lock();
+
+        // Stop the RTC updates while setting the time.
+        let status_b = access.read_status_b();
+        access.write_status_b(status_b | StatusB::SET);
+
+        access.write_register(Register::Second, self.second);
+        access.write_register(Register::Minute, self.minute);
+        access.write_register(Register::Hour, self.hour);
+        access.write_register(Register::Day, self.day);
+        access.write_register(Register::Month, self.month);
+        access.write_register(Register::Year, self.year as u8);
+        if let Some(century) = self.century {
+            access.write_century(century.get());
+        }
+
+        access.write_status_b(status_b - StatusB::SET);
+    }
+
     fn from_rtc_raw(access: &mut CmosAccess) -> Self {
         // Wait if the RTC updates are in progress.
         while access.read_status_a().contains(StatusA::UIP) {
@@ -239,6 +436,21 @@ impl CmosData {
         self.century = self.century.and_then(|c| NonZeroU8::new(bcd_to_binary(c.get())));
     }
 
+    /// Converts binary values to BCD values.
+    fn convert_binary_to_bcd(&mut self) {
+        fn binary_to_bcd(val: u8) -> u8 {
+            ((val / 10) << 4) | (val % 10)
+        }
+
+        self.second = binary_to_bcd(self.second);
+        self.minute = binary_to_bcd(self.minute);
+        self.hour = binary_to_bcd(self.hour & !Self::HOUR_IS_AFTERNOON) | (self.hour & Self::HOUR_IS_AFTERNOON);
+        self.day = binary_to_bcd(self.day);
+        self.month = binary_to_bcd(self.month);
+        self.year = binary_to_bcd(self.year as u8) as u16;
+        self.century = self.century.and_then(|c| NonZeroU8::new(binary_to_bcd(c.get())));
+    }
+
     const HOUR_IS_AFTERNOON: u8 = 0x80;
 
     /// Converts the 12-hour clock to the 24-hour clock.
@@ -248,6 +460,13 @@ impl CmosData {
         }
     }
 
+    /// Converts the 24-hour clock to the 12-hour clock.
+    fn convert_24_hour_to_12_hour(&mut self) {
+        if self.hour >= 12 {
+            self.hour = (self.hour - 12) | Self::HOUR_IS_AFTERNOON;
+        }
+    }
+
     /// Converts the year without the century (e.g., 10) to the year with the century (e.g., 2010).
     fn modify_year(&mut self) {
         const DEFAULT_21_CENTURY: u8 = 20;
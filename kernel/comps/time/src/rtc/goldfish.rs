@@ -1,15 +1,37 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use chrono::{DateTime, Datelike, Timelike};
+use chrono::{DateTime, Datelike, NaiveDate, Timelike};
 use log::warn;
-use ostd::{arch::boot::DEVICE_TREE, io::IoMem, mm::VmIoOnce};
+use ostd::{
+    arch::{
+        boot::DEVICE_TREE,
+        irq::{IRQ_CHIP, InterruptSourceInFdt, MappedIrqLine},
+    },
+    io::IoMem,
+    irq::IrqLine,
+    mm::VmIoOnce,
+};
 
-use crate::{rtc::Driver, SystemTime};
+use crate::{
+    SystemTime,
+    rtc::{Driver, RtcError},
+};
 
 pub struct RtcGoldfish {
     io_mem: IoMem,
+    /// The IRQ line of the alarm interrupt, if available.
+    irq_line: Option<MappedIrqLine>,
 }
 
+// Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/rtc/rtc-goldfish.c>
+const TIME_LOW_OFFSET: usize = 0x00;
+const TIME_HIGH_OFFSET: usize = 0x04;
+const ALARM_LOW_OFFSET: usize = 0x08;
+const ALARM_HIGH_OFFSET: usize = 0x0c;
+const IRQ_ENABLED_OFFSET: usize = 0x10;
+const CLEAR_ALARM_OFFSET: usize = 0x14;
+const CLEAR_INTERRUPT_OFFSET: usize = 0x1c;
+
 impl Driver for RtcGoldfish {
     fn try_new() -> Option<Self> {
         const FDT_COMPATIBLE: &str = "google,goldfish-rtc";
@@ -45,17 +67,41 @@ impl Driver for RtcGoldfish {
             return None;
         };
 
-        Some(Self { io_mem })
+        let interrupt_source = node
+            .interrupts()
+            .and_then(|mut interrupts| interrupts.next())
+            .zip(
+                node.property("interrupt-parent")
+                    .and_then(|prop| prop.as_usize()),
+            );
+        let irq_line = interrupt_source.and_then(|(interrupt, interrupt_parent)| {
+            let mut irq_line = IrqLine::alloc()
+                .and_then(|irq_line| {
+                    IRQ_CHIP.get().unwrap().map_fdt_pin_to(
+                        InterruptSourceInFdt {
+                            interrupt_parent: interrupt_parent as u32,
+                            interrupt: interrupt as u32,
+                        },
+                        irq_line,
+                    )
+                })
+                .ok()?;
+            let io_mem = io_mem.clone();
+            irq_line.on_active(move |_| handle_irq(&io_mem));
+            Some(irq_line)
+        });
+        if irq_line.is_none() {
+            warn!("Goldfish RTC IRQ line is not available, the alarm is disabled");
+        }
+
+        Some(Self { io_mem, irq_line })
     }
 
     fn read_rtc(&self) -> SystemTime {
-        const LOWER_HALF_OFFSET: usize = 0;
-        const HIGHER_HALF_OFFSET: usize = 4;
-
-        let mut last_time_high = self.io_mem.read_once(HIGHER_HALF_OFFSET).unwrap();
+        let mut last_time_high = self.io_mem.read_once(TIME_HIGH_OFFSET).unwrap();
         let timestamp = loop {
-            let time_low: u32 = self.io_mem.read_once(LOWER_HALF_OFFSET).unwrap();
-            let time_high: u32 = self.io_mem.read_once(HIGHER_HALF_OFFSET).unwrap();
+            let time_low: u32 = self.io_mem.read_once(TIME_LOW_OFFSET).unwrap();
+            let time_high: u32 = self.io_mem.read_once(TIME_HIGH_OFFSET).unwrap();
             if last_time_high == time_high {
                 break ((time_high as u64) << 32) | time_low as u64;
             }
@@ -76,4 +122,66 @@ impl Driver for RtcGoldfish {
             nanos: time.nanosecond() as u64,
         }
     }
+
+    fn set_rtc(&self, time: &SystemTime) -> Result<(), RtcError> {
+        let timestamp = to_timestamp_nanos(time)?;
+
+        self.io_mem
+            .write_once(TIME_HIGH_OFFSET, &((timestamp >> 32) as u32))
+            .unwrap();
+        self.io_mem
+            .write_once(TIME_LOW_OFFSET, &(timestamp as u32))
+            .unwrap();
+
+        Ok(())
+    }
+
+    fn set_alarm(&self, time: Option<&SystemTime>) -> Result<(), RtcError> {
+        if self.irq_line.is_none() {
+            return Err(RtcError::Unsupported);
+        }
+
+        let Some(time) = time else {
+            self.io_mem.write_once(IRQ_ENABLED_OFFSET, &0u32).unwrap();
+            self.io_mem.write_once(CLEAR_ALARM_OFFSET, &1u32).unwrap();
+            return Ok(());
+        };
+
+        let timestamp = to_timestamp_nanos(time)?;
+
+        // Writing the lower half of the alarm activates it.
+        self.io_mem
+            .write_once(ALARM_HIGH_OFFSET, &((timestamp >> 32) as u32))
+            .unwrap();
+        self.io_mem
+            .write_once(ALARM_LOW_OFFSET, &(timestamp as u32))
+            .unwrap();
+        self.io_mem.write_once(IRQ_ENABLED_OFFSET, &1u32).unwrap();
+
+        Ok(())
+    }
+}
+
+/// Handles the interrupts of the Goldfish RTC.
+fn handle_irq(io_mem: &IoMem) {
+    io_mem.write_once(CLEAR_INTERRUPT_OFFSET, &1u32).unwrap();
+
+    super::notify_alarm();
+}
+
+/// Converts the time to the number of nanoseconds since the Unix epoch.
+fn to_timestamp_nanos(time: &SystemTime) -> Result<u64, RtcError> {
+    let timestamp = NaiveDate::from_ymd_opt(time.year as i32, time.month as u32, time.day as u32)
+        .and_then(|date| {
+            date.and_hms_nano_opt(
+                time.hour as u32,
+                time.minute as u32,
+                time.second as u32,
+                time.nanos as u32,
+            )
+        })
+        .and_then(|time| time.and_utc().timestamp_nanos_opt())
+        .ok_or(RtcError::InvalidTime)?;
+
+    u64::try_from(timestamp).map_err(|_| RtcError::InvalidTime)
 }
@@ -2,7 +2,10 @@
 
 use ostd::{arch::boot::DEVICE_TREE, io::IoMem, mm::VmIoOnce};
 
-use crate::{rtc::Driver, SystemTime};
+use crate::{
+    SystemTime,
+    rtc::{Driver, RtcError},
+};
 
 pub struct RtcLoongson {
     io_mem: IoMem,
@@ -63,4 +66,29 @@ impl Driver for RtcLoongson {
             nanos,
         }
     }
+
+    fn set_rtc(&self, time: &SystemTime) -> Result<(), RtcError> {
+        const SYS_TOYWRITE0: usize = 0x24;
+        const SYS_TOYWRITE1: usize = 0x28;
+
+        let Some(toy_year) = time.year.checked_sub(1900) else {
+            return Err(RtcError::InvalidTime);
+        };
+
+        // Write the Time of Year (TOY) counter in the same format as it is read
+        // Reference: <https://loongson.github.io/LoongArch-Documentation/Loongson-7A1000-usermanual-EN.html#rtc>
+        let sys_toywrite0 = ((time.month as u32) << 26)
+            | ((time.day as u32) << 21)
+            | ((time.hour as u32) << 16)
+            | ((time.minute as u32) << 10)
+            | ((time.second as u32) << 4);
+        self.io_mem
+            .write_once(SYS_TOYWRITE0, &sys_toywrite0)
+            .unwrap();
+        self.io_mem
+            .write_once(SYS_TOYWRITE1, &(toy_year as u32))
+            .unwrap();
+
+        Ok(())
+    }
 }
@@ -14,6 +14,37 @@ pub trait Driver {
 
     /// Reads RTC.
     fn read_rtc(&self) -> SystemTime;
+
+    /// Sets RTC.
+    fn set_rtc(&self, time: &SystemTime) -> Result<(), RtcError>;
+
+    /// Sets the alarm of RTC, or disables the alarm if `time` is `None`.
+    ///
+    /// The alarm fires only once. When it fires, the driver should call [`notify_alarm`].
+    fn set_alarm(&self, _time: Option<&SystemTime>) -> Result<(), RtcError> {
+        Err(RtcError::Unsupported)
+    }
+}
+
+/// Errors that may occur when operating the RTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RtcError {
+    /// There are no RTC devices.
+    NoDevice,
+    /// The operation is not supported by the RTC device.
+    Unsupported,
+    /// The time cannot be represented by the RTC device.
+    InvalidTime,
+}
+
+/// Notifies that the alarm of the RTC has fired.
+///
+/// This is called in the interrupt context.
+#[cfg_attr(target_arch = "loongarch64", expect(dead_code))]
+fn notify_alarm() {
+    if let Some(handler) = crate::RTC_ALARM_HANDLER_FN.get() {
+        handler();
+    }
 }
 
 macro_rules! declare_rtc_drivers {
@@ -23,18 +54,16 @@ macro_rules! declare_rtc_drivers {
             mod $module;
         )*
 
-        pub fn init_rtc_driver() -> Arc<dyn Driver + Send + Sync> {
+        pub fn init_rtc_driver() -> Option<Arc<dyn Driver + Send + Sync>> {
             // iterate all possible drivers and pick one that can be initialized
             $(
                 #[cfg $cfg]
                 if let Some(driver) = $module::$name::try_new() {
-                    return Arc::new(driver);
+                    return Some(Arc::new(driver));
                 }
             )*
 
-            log::warn!("No RTC device found, falling back to a dummy RTC");
-
-            Arc::new(RtcDummy)
+            None
         }
     }
 }
@@ -44,23 +73,3 @@ declare_rtc_drivers! {
     #[cfg(target_arch = "riscv64")] goldfish::RtcGoldfish,
     #[cfg(target_arch = "loongarch64")] loongson::RtcLoongson,
 }
-
-struct RtcDummy;
-
-impl Driver for RtcDummy {
-    fn try_new() -> Option<Self> {
-        Some(Self)
-    }
-
-    fn read_rtc(&self) -> SystemTime {
-        SystemTime {
-            year: 1970,
-            month: 1,
-            day: 1,
-            hour: 0,
-            minute: 0,
-            second: 0,
-            nanos: 0,
-        }
-    }
-}
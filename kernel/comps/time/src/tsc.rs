@@ -3,16 +3,15 @@
 //! This module provide a instance of `ClockSource` based on TSC.
 
 use alloc::sync::Arc;
-use core::sync::atomic::{AtomicU64, Ordering};
 
 use ostd::{
     arch::{read_tsc, tsc_freq},
-    timer::{self, TIMER_FREQ},
+    timer,
 };
 use spin::Once;
 
 use crate::{
-    START_TIME, VDSO_DATA_HIGH_RES_UPDATE_FN,
+    START_TIME, SystemTime, VDSO_DATA_HIGH_RES_UPDATE_FN,
     clocksource::{ClockSource, Instant},
 };
 
@@ -43,7 +42,7 @@ fn calibrate() {
     let clock = CLOCK.get().unwrap();
     let cycles = clock.read_cycles();
     clock.calibrate(cycles);
-    START_TIME.call_once(|| crate::RTC_DRIVER.get().unwrap().read_rtc());
+    START_TIME.call_once(|| crate::read_rtc().unwrap_or(SystemTime::UNIX_EPOCH));
 }
 
 /// Reads an `Instant` of the TSC clocksource.
@@ -63,8 +62,6 @@ fn update_clocksource() {
     }
 }
 
-static TSC_UPDATE_COUNTER: AtomicU64 = AtomicU64::new(1);
-
 fn init_timer() {
     // The `max_delay_secs` should be set as `clock.max_delay_secs() >> 1` or something much smaller than `max_delay_secs`.
     // This is because the initialization of this timer occurs during system startup,
@@ -73,12 +70,15 @@ fn init_timer() {
     // If without KVM, the delayed time will be larger.
     // TODO: This is a temporary solution, and should be modified in the future.
     let max_delay_secs = CLOCK.get().unwrap().max_delay_secs() >> 1;
-    let delay_counts = TIMER_FREQ * max_delay_secs;
+    let delay_cycles = tsc_freq() * max_delay_secs;
 
+    // The ticks may be skipped on idle CPUs, so the elapsed cycles are checked instead of
+    // counting the ticks.
     let update = move || {
-        let counter = TSC_UPDATE_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let clock = CLOCK.get().unwrap();
+        let (_, last_cycles) = clock.last_record();
 
-        if counter.is_multiple_of(delay_counts) {
+        if clock.read_cycles().wrapping_sub(last_cycles) >= delay_cycles {
             update_clocksource();
         }
     };
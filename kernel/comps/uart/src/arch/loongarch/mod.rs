@@ -6,6 +6,7 @@ use crate::{
     CONSOLE_NAME,
     alloc::string::ToString,
     console::{Uart, UartConsole},
+    register_device,
 };
 
 pub(super) fn init() {
@@ -16,6 +17,7 @@ pub(super) fn init() {
     let uart_console = UartConsole::new(uart);
 
     aster_console::register_device(CONSOLE_NAME.to_string(), uart_console.clone());
+    register_device(0, uart_console.clone());
 
     // TODO: Set up the IRQ line and handle the received data.
     // Suppress the dead code warnings of the related methods.
@@ -16,6 +16,7 @@ use spin::Once;
 use crate::{
     CONSOLE_NAME,
     console::{Uart, UartConsole},
+    register_device,
 };
 
 /// Access to serial registers via `IoMem`.
@@ -83,6 +84,7 @@ pub(super) fn init(fdt_node: FdtNode) {
     let uart_console = UartConsole::new(SpinLock::new(uart));
 
     aster_console::register_device(CONSOLE_NAME.to_string(), uart_console.clone());
+    register_device(0, uart_console.clone());
 
     let cloned_uart_console = uart_console.clone();
     irq_line.on_active(move |_| cloned_uart_console.trigger_input_callbacks());
@@ -1,50 +1,136 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use alloc::string::ToString;
+use alloc::{string::ToString, sync::Arc, vec::Vec};
 
 use ostd::{
     arch::{
+        device::io_port::ReadWriteAccess,
         irq::{IRQ_CHIP, MappedIrqLine},
         serial::SERIAL_PORT,
     },
+    console::uart_ns16650a::{Ns16550aAccess, Ns16550aRegister, Ns16550aUart},
+    io::IoPort,
     irq::IrqLine,
+    sync::SpinLock,
 };
 use spin::Once;
 
 use crate::{
     CONSOLE_NAME,
     console::{Uart, UartConsole},
+    register_device,
 };
 
-/// ISA interrupt number for UART serial.
-// FIXME: The interrupt number should be retrieved from the ACPI table instead of being hard-coded.
+/// ISA interrupt number for the first UART serial (COM1).
+// FIXME: The interrupt numbers should be retrieved from the ACPI table instead of being
+// hard-coded.
 const ISA_INTR_NUM: u8 = 4;
 
-/// IRQ line for UART serial.
-static IRQ_LINE: Once<MappedIrqLine> = Once::new();
+/// Port numbers, I/O port bases, and ISA interrupt numbers for other UART serials (COM2, COM3,
+/// and COM4).
+///
+/// Reference: <https://wiki.osdev.org/Serial_Ports>.
+const OTHER_PORTS: [(u32, u16, u8); 3] = [(1, 0x2F8, 3), (2, 0x3E8, 4), (3, 0x2E8, 3)];
+
+/// IRQ lines for UART serials.
+///
+/// UART serials that use the same ISA interrupt number share an IRQ line.
+static IRQ_LINES: Once<Vec<(u8, MappedIrqLine)>> = Once::new();
+
+/// Access to serial registers via I/O ports acquired from the I/O port allocator.
+struct SerialAccess {
+    ports: [IoPort<u8, ReadWriteAccess>; 7],
+}
+
+impl SerialAccess {
+    fn acquire(base: u16) -> Option<Self> {
+        let mut ports = Vec::with_capacity(7);
+        for offset in 0..7 {
+            ports.push(IoPort::acquire(base + offset).ok()?);
+        }
+
+        Some(Self {
+            ports: ports.try_into().ok()?,
+        })
+    }
+}
+
+impl Ns16550aAccess for SerialAccess {
+    fn read(&self, reg: Ns16550aRegister) -> u8 {
+        self.ports[reg as usize].read()
+    }
+
+    fn write(&mut self, reg: Ns16550aRegister, val: u8) {
+        self.ports[reg as usize].write(val);
+    }
+}
 
 pub(super) fn init() {
-    let Some(uart) = SERIAL_PORT.get() else {
-        return;
-    };
+    let mut irq_lines = Vec::new();
 
-    let Ok(mut irq_line) = IrqLine::alloc().and_then(|irq_line| {
-        IRQ_CHIP
-            .get()
-            .unwrap()
-            .map_isa_pin_to(irq_line, ISA_INTR_NUM)
-    }) else {
-        log::info!("[UART]: IRQ line is not available");
-        return;
-    };
+    if let Some(uart) = SERIAL_PORT.get() {
+        let uart_console = UartConsole::new(uart);
+        if attach_irq(&mut irq_lines, ISA_INTR_NUM, uart_console.clone()) {
+            aster_console::register_device(CONSOLE_NAME.to_string(), uart_console.clone());
+            register_device(0, uart_console);
+            uart.flush();
+
+            log::info!("[UART]: Registered NS16550A as a console");
+        }
+    }
+
+    for (port_num, base, isa_intr_num) in OTHER_PORTS {
+        let Some(access) = SerialAccess::acquire(base) else {
+            log::info!("[UART]: I/O ports are not available for 0x{:x}", base);
+            continue;
+        };
 
-    let uart_console = UartConsole::new(uart);
+        let mut uart = Ns16550aUart::new(access);
+        if !uart.probe() {
+            continue;
+        }
+        uart.init();
 
-    aster_console::register_device(CONSOLE_NAME.to_string(), uart_console.clone());
+        let uart_console = UartConsole::new(SpinLock::new(uart));
+        if attach_irq(&mut irq_lines, isa_intr_num, uart_console.clone()) {
+            register_device(port_num, uart_console.clone());
+            uart_console.uart().flush();
 
-    irq_line.on_active(move |_| uart_console.trigger_input_callbacks());
-    IRQ_LINE.call_once(move || irq_line);
-    uart.flush();
+            log::info!("[UART]: Registered NS16550A at 0x{:x}", base);
+        }
+    }
+
+    IRQ_LINES.call_once(move || irq_lines);
+}
+
+/// Handles the IRQs of the ISA interrupt number for the UART console.
+///
+/// This function returns `false` if no IRQ line is available for the ISA interrupt number.
+fn attach_irq<U: Uart + Send + Sync + 'static>(
+    irq_lines: &mut Vec<(u8, MappedIrqLine)>,
+    isa_intr_num: u8,
+    uart_console: Arc<UartConsole<U>>,
+) -> bool {
+    let index = match irq_lines.iter().position(|(num, _)| *num == isa_intr_num) {
+        Some(index) => index,
+        None => {
+            let Ok(irq_line) = IrqLine::alloc().and_then(|irq_line| {
+                IRQ_CHIP
+                    .get()
+                    .unwrap()
+                    .map_isa_pin_to(irq_line, isa_intr_num)
+            }) else {
+                log::info!("[UART]: IRQ line is not available");
+                return false;
+            };
+            irq_lines.push((isa_intr_num, irq_line));
+            irq_lines.len() - 1
+        }
+    };
 
-    log::info!("[UART]: Registered NS16550A as a console");
+    // Multiple UART serials may share the IRQ line, so we need to check all of them.
+    irq_lines[index]
+        .1
+        .on_active(move |_| uart_console.trigger_input_callbacks());
+    true
 }
@@ -6,11 +6,16 @@ use core::fmt::Debug;
 use aster_console::{AnyConsoleDevice, ConsoleCallback};
 use inherit_methods_macro::inherit_methods;
 use ostd::{
-    console::uart_ns16650a::{Ns16550aAccess, Ns16550aUart},
+    console::uart_ns16650a::{
+        NS16550A_BASE_BAUD, Ns16550aAccess, Ns16550aLineConfig, Ns16550aModemCtrl,
+        Ns16550aModemStat, Ns16550aParity, Ns16550aUart,
+    },
     mm::VmReader,
     sync::{LocalIrqDisabled, SpinLock},
 };
 
+use crate::{AnyUartDevice, LineConfig, ModemLines, Parity};
+
 /// A UART console.
 pub(super) struct UartConsole<U: Uart> {
     uart: U,
@@ -33,7 +38,7 @@ impl<U: Uart> UartConsole<U> {
     }
 
     /// Returns a reference to the UART instance.
-    #[cfg_attr(not(target_arch = "riscv64"), expect(dead_code))]
+    #[cfg_attr(target_arch = "loongarch64", expect(dead_code))]
     pub(super) fn uart(&self) -> &U {
         &self.uart
     }
@@ -70,6 +75,20 @@ impl<U: Uart + Send + Sync + 'static> AnyConsoleDevice for UartConsole<U> {
     }
 }
 
+impl<U: Uart + Send + Sync + 'static> AnyUartDevice for UartConsole<U> {
+    fn set_line_config(&self, config: &LineConfig) -> u32 {
+        self.uart.set_line_config(config)
+    }
+
+    fn modem_lines(&self) -> ModemLines {
+        self.uart.modem_lines()
+    }
+
+    fn set_modem_lines(&self, lines: ModemLines) {
+        self.uart.set_modem_lines(lines);
+    }
+}
+
 /// A trait that abstracts UART devices.
 pub(super) trait Uart {
     /// Sends a sequence of bytes to UART.
@@ -84,6 +103,15 @@ pub(super) trait Uart {
     /// This method should be called after setting up the IRQ handlers to ensure new received data
     /// will trigger IRQs.
     fn flush(&self);
+
+    /// Sets the line configuration and returns the actual baud rate.
+    fn set_line_config(&self, config: &LineConfig) -> u32;
+
+    /// Returns the states of the modem lines.
+    fn modem_lines(&self) -> ModemLines;
+
+    /// Sets the states of the output modem lines.
+    fn set_modem_lines(&self, lines: ModemLines);
 }
 
 impl<A: Ns16550aAccess> Uart for SpinLock<Ns16550aUart<A>, LocalIrqDisabled> {
@@ -118,6 +146,53 @@ impl<A: Ns16550aAccess> Uart for SpinLock<Ns16550aUart<A>, LocalIrqDisabled> {
 
         while uart.recv().is_some() {}
     }
+
+    fn set_line_config(&self, config: &LineConfig) -> u32 {
+        let baud_rate = config.baud_rate.max(1);
+        // Choose the divisor that generates the closest baud rate.
+        let divisor = (NS16550A_BASE_BAUD + baud_rate / 2) / baud_rate;
+        let divisor = divisor.clamp(1, u16::MAX as u32) as u16;
+
+        let parity = match config.parity {
+            Parity::None => Ns16550aParity::None,
+            Parity::Odd => Ns16550aParity::Odd,
+            Parity::Even => Ns16550aParity::Even,
+        };
+        let ns16550a_config = Ns16550aLineConfig {
+            divisor,
+            data_bits: config.data_bits,
+            parity,
+            two_stop_bits: config.two_stop_bits,
+        };
+        self.lock().set_line_config(&ns16550a_config);
+
+        NS16550A_BASE_BAUD / divisor as u32
+    }
+
+    fn modem_lines(&self) -> ModemLines {
+        let uart = self.lock();
+
+        let modem_ctrl = uart.modem_ctrl();
+        let modem_stat = uart.modem_stat();
+
+        let mut lines = ModemLines::empty();
+        lines.set(ModemLines::DTR, modem_ctrl.contains(Ns16550aModemCtrl::DTR));
+        lines.set(ModemLines::RTS, modem_ctrl.contains(Ns16550aModemCtrl::RTS));
+        lines.set(ModemLines::CTS, modem_stat.contains(Ns16550aModemStat::CTS));
+        lines.set(ModemLines::DSR, modem_stat.contains(Ns16550aModemStat::DSR));
+        lines.set(ModemLines::RI, modem_stat.contains(Ns16550aModemStat::RI));
+        lines.set(ModemLines::CD, modem_stat.contains(Ns16550aModemStat::DCD));
+        lines
+    }
+
+    fn set_modem_lines(&self, lines: ModemLines) {
+        let mut uart = self.lock();
+
+        let mut modem_ctrl = uart.modem_ctrl();
+        modem_ctrl.set(Ns16550aModemCtrl::DTR, lines.contains(ModemLines::DTR));
+        modem_ctrl.set(Ns16550aModemCtrl::RTS, lines.contains(ModemLines::RTS));
+        uart.set_modem_ctrl(modem_ctrl);
+    }
 }
 
 #[inherit_methods(from = "(**self)")]
@@ -125,4 +200,7 @@ impl<A: Ns16550aAccess> Uart for &SpinLock<Ns16550aUart<A>, LocalIrqDisabled> {
     fn send(&self, buf: &[u8]);
     fn recv(&self, buf: &mut [u8]) -> usize;
     fn flush(&self);
+    fn set_line_config(&self, config: &LineConfig) -> u32;
+    fn modem_lines(&self) -> ModemLines;
+    fn set_modem_lines(&self, lines: ModemLines);
 }
@@ -0,0 +1,72 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_console::AnyConsoleDevice;
+use bitflags::bitflags;
+
+/// A UART device.
+///
+/// Besides sending and receiving data as a console device, a UART device allows its serial line
+/// to be configured (e.g., by the termios of the corresponding TTY).
+pub trait AnyUartDevice: AnyConsoleDevice {
+    /// Sets the line configuration.
+    ///
+    /// This method returns the actual baud rate, which can differ from the requested one if the
+    /// device cannot generate the requested baud rate precisely.
+    fn set_line_config(&self, config: &LineConfig) -> u32;
+
+    /// Returns the states of the modem lines.
+    fn modem_lines(&self) -> ModemLines;
+
+    /// Sets the states of the output modem lines.
+    ///
+    /// Only [`ModemLines::DTR`] and [`ModemLines::RTS`] are output modem lines. Other bits are
+    /// ignored.
+    fn set_modem_lines(&self, lines: ModemLines);
+}
+
+/// The line configuration of a UART.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LineConfig {
+    /// The baud rate in bits per second.
+    pub baud_rate: u32,
+    /// The number of data bits, from 5 to 8.
+    pub data_bits: u8,
+    /// The parity.
+    pub parity: Parity,
+    /// Whether two stop bits (instead of one) are used.
+    pub two_stop_bits: bool,
+}
+
+/// The parity of a UART.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Parity {
+    /// No parity bit.
+    None,
+    /// Odd parity.
+    Odd,
+    /// Even parity.
+    Even,
+}
+
+bitflags! {
+    /// The modem lines of a UART.
+    ///
+    /// The values are the same as the `TIOCM_*` constants in Linux.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/asm-generic/termios.h>.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct ModemLines: u32 {
+        /// Data terminal ready (DTR).
+        const DTR = 0x002;
+        /// Request to send (RTS).
+        const RTS = 0x004;
+        /// Clear to send (CTS).
+        const CTS = 0x020;
+        /// Carrier detect (CD).
+        const CD  = 0x040;
+        /// Ring indicator (RI).
+        const RI  = 0x080;
+        /// Data set ready (DSR).
+        const DSR = 0x100;
+    }
+}
@@ -7,7 +7,12 @@
 
 extern crate alloc;
 
+use alloc::{collections::btree_map::BTreeMap, sync::Arc, vec::Vec};
+
 use component::{ComponentInitError, init_component};
+use ostd::sync::{LocalIrqDisabled, SpinLock};
+
+pub use self::device::{AnyUartDevice, LineConfig, ModemLines, Parity};
 
 #[cfg_attr(target_arch = "x86_64", path = "arch/x86/mod.rs")]
 #[cfg_attr(target_arch = "riscv64", path = "arch/riscv/mod.rs")]
@@ -15,9 +20,33 @@ use component::{ComponentInitError, init_component};
 mod arch;
 
 mod console;
+mod device;
 
+/// The name of the console device for the first UART.
+///
+/// Only the first UART is registered as a console device, so the kernel messages will not be
+/// sent to other UARTs.
 pub const CONSOLE_NAME: &str = "Uart-Console";
 
+static DEVICES: SpinLock<BTreeMap<u32, Arc<dyn AnyUartDevice>>, LocalIrqDisabled> =
+    SpinLock::new(BTreeMap::new());
+
+/// Returns all the UART devices with their port numbers, sorted by the port numbers.
+///
+/// The port number determines the name of the device (e.g., the device whose port number is 1 is
+/// `ttyS1`).
+pub fn all_devices() -> Vec<(u32, Arc<dyn AnyUartDevice>)> {
+    DEVICES
+        .lock()
+        .iter()
+        .map(|(port_num, device)| (*port_num, device.clone()))
+        .collect()
+}
+
+fn register_device(port_num: u32, device: Arc<dyn AnyUartDevice>) {
+    DEVICES.lock().insert(port_num, device);
+}
+
 #[init_component]
 fn init() -> Result<(), ComponentInitError> {
     arch::init();
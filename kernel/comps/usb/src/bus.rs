@@ -0,0 +1,120 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{sync::Arc, vec::Vec};
+
+use log::{info, warn};
+use ostd::sync::Mutex;
+
+use crate::{
+    UsbError,
+    descriptor::{InterfaceDescriptor, class},
+    device::UsbDevice,
+};
+
+/// A driver of the USB interfaces.
+pub trait UsbDriver: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Probes an interface of a device, and binds the driver to it if it is supported.
+    ///
+    /// [`UsbError::NotSupported`] should be returned if the driver does not support the
+    /// interface, in which case the other drivers are probed.
+    fn probe(
+        &self,
+        device: &Arc<UsbDevice>,
+        interface: &InterfaceDescriptor,
+    ) -> Result<(), UsbError>;
+
+    /// Unbinds the driver from an interface of a device that is disconnected.
+    ///
+    /// All the transfers of the device fail with [`UsbError::Disconnected`] when this method is
+    /// called.
+    fn disconnect(&self, device: &Arc<UsbDevice>, interface: &InterfaceDescriptor);
+}
+
+static DRIVERS: Mutex<Vec<Arc<dyn UsbDriver>>> = Mutex::new(Vec::new());
+static DEVICES: Mutex<Vec<Arc<UsbDevice>>> = Mutex::new(Vec::new());
+
+/// Registers a driver, which is probed with the existing devices and the new devices.
+pub fn register_driver(driver: Arc<dyn UsbDriver>) {
+    let mut drivers = DRIVERS.lock();
+    let devices = DEVICES.lock().clone();
+    for device in devices.iter() {
+        bind(device, core::slice::from_ref(&driver));
+    }
+    drivers.push(driver);
+}
+
+/// Returns all the connected devices.
+pub fn all_devices() -> Vec<Arc<UsbDevice>> {
+    DEVICES.lock().clone()
+}
+
+pub(crate) fn add_device(device: &Arc<UsbDevice>) {
+    DEVICES.lock().push(device.clone());
+
+    let drivers = DRIVERS.lock();
+    bind(device, &drivers);
+}
+
+pub(crate) fn remove_device(device: &Arc<UsbDevice>) {
+    DEVICES.lock().retain(|other| !Arc::ptr_eq(other, device));
+
+    let bindings = core::mem::take(&mut *device.bindings.lock());
+    for (number, driver) in bindings {
+        let interface = device
+            .configuration()
+            .interfaces
+            .iter()
+            .find(|interface| interface.number == number && interface.alternate_setting == 0)
+            .unwrap();
+        driver.disconnect(device, interface);
+    }
+}
+
+/// Binds the drivers to the interfaces that are not bound yet.
+///
+/// The hub interfaces are handled by the subsystem itself.
+fn bind(device: &Arc<UsbDevice>, drivers: &[Arc<dyn UsbDriver>]) {
+    if device.is_disconnected() {
+        return;
+    }
+
+    let mut bindings = device.bindings.lock();
+    let interfaces = device
+        .configuration()
+        .interfaces
+        .iter()
+        .filter(|interface| interface.alternate_setting == 0 && interface.class != class::HUB);
+    for interface in interfaces {
+        if bindings
+            .iter()
+            .any(|(number, _)| *number == interface.number)
+        {
+            continue;
+        }
+
+        for driver in drivers {
+            match driver.probe(device, interface) {
+                Ok(()) => {
+                    info!(
+                        "USB device {}: interface {} is bound to {}",
+                        device.name(),
+                        interface.number,
+                        driver.name()
+                    );
+                    bindings.push((interface.number, driver.clone()));
+                    break;
+                }
+                Err(UsbError::NotSupported) => (),
+                Err(err) => warn!(
+                    "USB device {}: {} failed to probe interface {}: {:?}",
+                    device.name(),
+                    driver.name(),
+                    interface.number,
+                    err
+                ),
+            }
+        }
+    }
+}
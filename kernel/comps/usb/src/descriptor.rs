@@ -0,0 +1,236 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The standard descriptors.
+//!
+//! Reference: Universal Serial Bus Specification, Revision 2.0, Section 9.6.
+
+use alloc::vec::Vec;
+
+use crate::UsbError;
+
+/// The descriptor types.
+pub mod descriptor_type {
+    pub const DEVICE: u8 = 1;
+    pub const CONFIGURATION: u8 = 2;
+    pub const STRING: u8 = 3;
+    pub const INTERFACE: u8 = 4;
+    pub const ENDPOINT: u8 = 5;
+    /// The SuperSpeed endpoint companion descriptor, which follows an endpoint descriptor.
+    pub const SS_ENDPOINT_COMPANION: u8 = 48;
+}
+
+/// The class codes.
+pub mod class {
+    /// The class is defined by each interface.
+    pub const PER_INTERFACE: u8 = 0x00;
+    pub const HID: u8 = 0x03;
+    pub const MASS_STORAGE: u8 = 0x08;
+    pub const HUB: u8 = 0x09;
+    pub const VENDOR_SPECIFIC: u8 = 0xFF;
+}
+
+/// A device descriptor.
+#[derive(Debug, Clone)]
+pub struct DeviceDescriptor {
+    /// The USB version in BCD, e.g., `0x0200` for USB 2.0.
+    pub usb_version: u16,
+    pub class: u8,
+    pub subclass: u8,
+    pub protocol: u8,
+    /// The maximum packet size of the default control endpoint, which is an exponent for the
+    /// SuperSpeed devices.
+    pub max_packet_size0: u8,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    /// The device version in BCD.
+    pub device_version: u16,
+    pub manufacturer_index: u8,
+    pub product_index: u8,
+    pub serial_number_index: u8,
+    pub nr_configurations: u8,
+}
+
+impl DeviceDescriptor {
+    /// The size of a device descriptor.
+    pub const SIZE: usize = 18;
+
+    pub(crate) fn parse(bytes: &[u8]) -> Result<Self, UsbError> {
+        if bytes.len() < Self::SIZE || bytes[1] != descriptor_type::DEVICE {
+            return Err(UsbError::NotSupported);
+        }
+
+        Ok(Self {
+            usb_version: read_u16(bytes, 2),
+            class: bytes[4],
+            subclass: bytes[5],
+            protocol: bytes[6],
+            max_packet_size0: bytes[7],
+            vendor_id: read_u16(bytes, 8),
+            product_id: read_u16(bytes, 10),
+            device_version: read_u16(bytes, 12),
+            manufacturer_index: bytes[14],
+            product_index: bytes[15],
+            serial_number_index: bytes[16],
+            nr_configurations: bytes[17],
+        })
+    }
+}
+
+/// A configuration descriptor, with the interface descriptors and the endpoint descriptors
+/// that follow it.
+#[derive(Debug, Clone)]
+pub struct ConfigurationDescriptor {
+    /// The value that selects the configuration with `SET_CONFIGURATION`.
+    pub value: u8,
+    pub attributes: u8,
+    /// The maximum power consumption in 2-mA units (or 8-mA units for SuperSpeed devices).
+    pub max_power: u8,
+    /// The interfaces, including all the alternate settings.
+    pub interfaces: Vec<InterfaceDescriptor>,
+}
+
+impl ConfigurationDescriptor {
+    /// The size of a configuration descriptor without the following descriptors.
+    pub const SIZE: usize = 9;
+
+    pub(crate) fn parse(bytes: &[u8]) -> Result<Self, UsbError> {
+        if bytes.len() < Self::SIZE || bytes[1] != descriptor_type::CONFIGURATION {
+            return Err(UsbError::NotSupported);
+        }
+
+        let mut config = Self {
+            value: bytes[5],
+            attributes: bytes[7],
+            max_power: bytes[8],
+            interfaces: Vec::new(),
+        };
+
+        let mut offset = bytes[0] as usize;
+        while offset + 2 <= bytes.len() {
+            let len = bytes[offset] as usize;
+            if len < 2 || offset + len > bytes.len() {
+                return Err(UsbError::NotSupported);
+            }
+            let desc = &bytes[offset..offset + len];
+            offset += len;
+
+            match desc[1] {
+                descriptor_type::INTERFACE if len >= 9 => {
+                    config.interfaces.push(InterfaceDescriptor {
+                        number: desc[2],
+                        alternate_setting: desc[3],
+                        class: desc[5],
+                        subclass: desc[6],
+                        protocol: desc[7],
+                        endpoints: Vec::new(),
+                        extra: Vec::new(),
+                    });
+                }
+                descriptor_type::ENDPOINT if len >= 7 => {
+                    let Some(interface) = config.interfaces.last_mut() else {
+                        continue;
+                    };
+                    let max_packet_size = read_u16(desc, 4);
+                    let mut endpoint = EndpointDescriptor {
+                        address: desc[2],
+                        attributes: desc[3],
+                        max_packet_size: max_packet_size & 0x7FF,
+                        interval: desc[6],
+                        max_burst: 0,
+                    };
+                    // The high-speed periodic endpoints may have additional transactions in
+                    // each microframe.
+                    if endpoint.is_periodic() {
+                        endpoint.max_burst = ((max_packet_size >> 11) & 0x3) as u8;
+                    }
+                    interface.endpoints.push(endpoint);
+                }
+                descriptor_type::SS_ENDPOINT_COMPANION if len >= 6 => {
+                    let endpoint = config
+                        .interfaces
+                        .last_mut()
+                        .and_then(|interface| interface.endpoints.last_mut());
+                    if let Some(endpoint) = endpoint {
+                        endpoint.max_burst = desc[2];
+                    }
+                }
+                _ => {
+                    if let Some(interface) = config.interfaces.last_mut() {
+                        interface.extra.extend_from_slice(desc);
+                    }
+                }
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// An interface descriptor, with the endpoint descriptors that follow it.
+#[derive(Debug, Clone)]
+pub struct InterfaceDescriptor {
+    pub number: u8,
+    pub alternate_setting: u8,
+    pub class: u8,
+    pub subclass: u8,
+    pub protocol: u8,
+    /// The endpoints, excluding the default control endpoint.
+    pub endpoints: Vec<EndpointDescriptor>,
+    /// The class-specific or vendor-specific descriptors, e.g., the HID descriptor.
+    pub extra: Vec<u8>,
+}
+
+/// The transfer types of the endpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferType {
+    Control,
+    Isochronous,
+    Bulk,
+    Interrupt,
+}
+
+/// An endpoint descriptor.
+#[derive(Debug, Clone)]
+pub struct EndpointDescriptor {
+    /// The endpoint address, whose bit 7 is set for the IN endpoints.
+    pub address: u8,
+    pub attributes: u8,
+    pub max_packet_size: u16,
+    /// The polling interval of the periodic endpoints, whose unit depends on the speed.
+    pub interval: u8,
+    /// The number of additional packets in a burst (or in a microframe for the high-speed
+    /// periodic endpoints).
+    pub max_burst: u8,
+}
+
+impl EndpointDescriptor {
+    /// Returns the endpoint number.
+    pub fn number(&self) -> u8 {
+        self.address & 0xF
+    }
+
+    /// Returns whether the endpoint transfers data from the device to the host.
+    pub fn is_in(&self) -> bool {
+        self.address & 0x80 != 0
+    }
+
+    pub fn transfer_type(&self) -> TransferType {
+        match self.attributes & 0x3 {
+            0 => TransferType::Control,
+            1 => TransferType::Isochronous,
+            2 => TransferType::Bulk,
+            _ => TransferType::Interrupt,
+        }
+    }
+
+    fn is_periodic(&self) -> bool {
+        matches!(
+            self.transfer_type(),
+            TransferType::Isochronous | TransferType::Interrupt
+        )
+    }
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
@@ -0,0 +1,462 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{
+    boxed::Box,
+    string::String,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use log::warn;
+use ostd::{
+    mm::{HasDaddr, VmIo, dma::DmaCoherent},
+    sync::Mutex,
+};
+use spin::Once;
+
+use crate::{
+    UsbDriver, UsbError, UsbSpeed,
+    descriptor::{
+        ConfigurationDescriptor, DeviceDescriptor, EndpointDescriptor, TransferType, class,
+        descriptor_type,
+    },
+    hub::{self, Hub},
+    request::{FEATURE_ENDPOINT_HALT, SetupPacket, request, request_type},
+    transfer,
+    xhci::{CompletionHandler, DevicePath, Endpoint, HubInfo, Slot, XhciController},
+};
+
+/// The language ID of the string descriptors, which is English (United States).
+///
+/// Nearly all the devices support it, so the supported languages are not queried.
+const LANGUAGE_ID: u16 = 0x0409;
+
+/// The maximum size of the data received by an interrupt pipe at a time.
+const MAX_INTERRUPT_TRANSFER_SIZE: usize = 1024;
+
+/// A USB device, which is configured with its first configuration.
+pub struct UsbDevice {
+    name: String,
+    speed: UsbSpeed,
+    path: DevicePath,
+    /// The number of the hubs between the device and the root hub.
+    depth: u8,
+    slot: Slot,
+    descriptor: DeviceDescriptor,
+    configuration: ConfigurationDescriptor,
+    manufacturer: Option<String>,
+    product: Option<String>,
+    serial_number: Option<String>,
+    /// The hub functionality, if the device is a hub.
+    hub: Once<Arc<Hub>>,
+    /// The drivers that are bound to the interfaces, with the interface numbers.
+    pub(crate) bindings: Mutex<Vec<(u8, Arc<dyn UsbDriver>)>>,
+    is_disconnected: AtomicBool,
+}
+
+impl UsbDevice {
+    /// Enumerates a device that is attached to a port that has been reset.
+    pub(crate) fn enumerate(
+        controller: &Arc<XhciController>,
+        name: String,
+        path: DevicePath,
+        depth: u8,
+        speed: UsbSpeed,
+    ) -> Result<Arc<Self>, UsbError> {
+        let slot = Slot::enable(controller, &path, speed)?;
+
+        let (descriptor, configuration) = match configure(&slot, speed, depth) {
+            Ok(descriptors) => descriptors,
+            Err(err) => {
+                slot.disable();
+                return Err(err);
+            }
+        };
+        let manufacturer = read_string(&slot, descriptor.manufacturer_index);
+        let product = read_string(&slot, descriptor.product_index);
+        let serial_number = read_string(&slot, descriptor.serial_number_index);
+
+        Ok(Arc::new(Self {
+            name,
+            speed,
+            path,
+            depth,
+            slot,
+            descriptor,
+            configuration,
+            manufacturer,
+            product,
+            serial_number,
+            hub: Once::new(),
+            bindings: Mutex::new(Vec::new()),
+            is_disconnected: AtomicBool::new(false),
+        }))
+    }
+
+    /// Returns the name of the device, e.g., `1-2.3`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn speed(&self) -> UsbSpeed {
+        self.speed
+    }
+
+    pub fn descriptor(&self) -> &DeviceDescriptor {
+        &self.descriptor
+    }
+
+    /// Returns the active configuration.
+    pub fn configuration(&self) -> &ConfigurationDescriptor {
+        &self.configuration
+    }
+
+    pub fn manufacturer(&self) -> Option<&str> {
+        self.manufacturer.as_deref()
+    }
+
+    pub fn product(&self) -> Option<&str> {
+        self.product.as_deref()
+    }
+
+    pub fn serial_number(&self) -> Option<&str> {
+        self.serial_number.as_deref()
+    }
+
+    /// Returns whether the device is disconnected, after which all the transfers fail.
+    pub fn is_disconnected(&self) -> bool {
+        self.is_disconnected.load(Ordering::Relaxed)
+    }
+
+    /// Performs a control transfer whose data stage (if any) is IN, returning the number of the
+    /// received bytes.
+    pub fn control_in(
+        &self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        buf: &mut [u8],
+    ) -> Result<usize, UsbError> {
+        let setup = SetupPacket {
+            request_type: request_type | request_type::DIR_IN,
+            request,
+            value,
+            index,
+            length: 0,
+        };
+        transfer::control_in(&self.slot, setup, buf)
+    }
+
+    /// Performs a control transfer whose data stage (if any) is OUT.
+    pub fn control_out(
+        &self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        data: &[u8],
+    ) -> Result<(), UsbError> {
+        let setup = SetupPacket {
+            request_type: request_type & !request_type::DIR_IN,
+            request,
+            value,
+            index,
+            length: 0,
+        };
+        transfer::control_out(&self.slot, setup, data)
+    }
+
+    /// Receives data from a bulk or interrupt IN endpoint, returning the number of the received
+    /// bytes.
+    ///
+    /// The transfer ends early when the device sends a short packet. It blocks until the
+    /// device sends the data or it is disconnected.
+    pub fn bulk_in(&self, endpoint: u8, buf: &mut [u8]) -> Result<usize, UsbError> {
+        let endpoint = self.endpoint(endpoint, true)?;
+        transfer::normal_in(&self.slot, &endpoint, buf)
+    }
+
+    /// Sends data to a bulk or interrupt OUT endpoint, returning the number of the sent bytes.
+    pub fn bulk_out(&self, endpoint: u8, data: &[u8]) -> Result<usize, UsbError> {
+        let endpoint = self.endpoint(endpoint, false)?;
+        transfer::normal_out(&self.slot, &endpoint, data)
+    }
+
+    /// Clears the halt condition of an endpoint, e.g., after it stalls.
+    pub fn clear_halt(&self, endpoint: u8) -> Result<(), UsbError> {
+        self.control_out(
+            request_type::TYPE_STANDARD | request_type::RECIPIENT_ENDPOINT,
+            request::CLEAR_FEATURE,
+            FEATURE_ENDPOINT_HALT,
+            endpoint as u16,
+            &[],
+        )
+    }
+
+    /// Opens a pipe that polls an interrupt IN endpoint, which calls `callback` with the data
+    /// in the interrupt context.
+    ///
+    /// The pipe must be stopped with [`InterruptPipe::stop`] before it is dropped, or it keeps
+    /// polling until the device is disconnected.
+    pub fn open_interrupt_pipe(
+        self: &Arc<Self>,
+        endpoint: u8,
+        callback: Box<dyn Fn(&[u8]) + Send + Sync>,
+    ) -> Result<Arc<InterruptPipe>, UsbError> {
+        let desc = self
+            .endpoints()
+            .find(|desc| desc.address == endpoint)
+            .ok_or(UsbError::InvalidArgument)?;
+        if desc.transfer_type() != TransferType::Interrupt || !desc.is_in() {
+            return Err(UsbError::InvalidArgument);
+        }
+        let len = (desc.max_packet_size as usize * (desc.max_burst as usize + 1))
+            .min(MAX_INTERRUPT_TRANSFER_SIZE);
+
+        let pipe = Arc::new_cyclic(|weak_self| InterruptPipe {
+            device: Arc::downgrade(self),
+            endpoint: self.slot.endpoint(endpoint).unwrap(),
+            buffer: DmaCoherent::alloc(1, true).unwrap(),
+            len,
+            callback,
+            is_stopped: AtomicBool::new(false),
+            weak_self: weak_self.clone(),
+        });
+        pipe.submit()?;
+
+        Ok(pipe)
+    }
+
+    pub(crate) fn path(&self) -> &DevicePath {
+        &self.path
+    }
+
+    pub(crate) fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    pub(crate) fn slot(&self) -> &Slot {
+        &self.slot
+    }
+
+    pub(crate) fn slot_id(&self) -> u8 {
+        self.slot.slot_id()
+    }
+
+    pub(crate) fn hub(&self) -> Option<&Arc<Hub>> {
+        self.hub.get()
+    }
+
+    pub(crate) fn set_hub(&self, hub: Arc<Hub>) {
+        self.hub.call_once(|| hub);
+    }
+
+    /// Marks the device as disconnected, and fails the outstanding transfers.
+    pub(crate) fn disconnect(&self) {
+        self.is_disconnected.store(true, Ordering::Relaxed);
+        self.slot.disable();
+    }
+
+    /// Returns the endpoints of the interfaces in the active configuration.
+    fn endpoints(&self) -> impl Iterator<Item = &EndpointDescriptor> {
+        self.configuration
+            .interfaces
+            .iter()
+            .filter(|interface| interface.alternate_setting == 0)
+            .flat_map(|interface| interface.endpoints.iter())
+    }
+
+    fn endpoint(&self, address: u8, is_in: bool) -> Result<Arc<Endpoint>, UsbError> {
+        if (address & 0x80 != 0) != is_in {
+            return Err(UsbError::InvalidArgument);
+        }
+        self.slot.endpoint(address).ok_or(UsbError::InvalidArgument)
+    }
+}
+
+impl core::fmt::Debug for UsbDevice {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("UsbDevice")
+            .field("name", &self.name)
+            .field("speed", &self.speed)
+            .field("vendor_id", &self.descriptor.vendor_id)
+            .field("product_id", &self.descriptor.product_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Reads the descriptors and selects the first configuration.
+fn configure(
+    slot: &Slot,
+    speed: UsbSpeed,
+    depth: u8,
+) -> Result<(DeviceDescriptor, ConfigurationDescriptor), UsbError> {
+    // The maximum packet size of the default control endpoint is in the first 8 bytes.
+    let mut buf = [0u8; DeviceDescriptor::SIZE];
+    get_descriptor(slot, descriptor_type::DEVICE, 0, &mut buf[..8])?;
+    let max_packet_size = if speed.is_superspeed() {
+        1u16 << buf[7].min(9)
+    } else {
+        buf[7] as u16
+    };
+    slot.set_ep0_max_packet_size(max_packet_size)?;
+
+    let len = get_descriptor(slot, descriptor_type::DEVICE, 0, &mut buf)?;
+    let descriptor = DeviceDescriptor::parse(&buf[..len])?;
+    if descriptor.nr_configurations == 0 {
+        return Err(UsbError::NotSupported);
+    }
+
+    let mut header = [0u8; ConfigurationDescriptor::SIZE];
+    get_descriptor(slot, descriptor_type::CONFIGURATION, 0, &mut header)?;
+    let total_len = u16::from_le_bytes([header[2], header[3]]) as usize;
+    let mut bytes = alloc::vec![0u8; total_len.max(ConfigurationDescriptor::SIZE)];
+    let len = get_descriptor(slot, descriptor_type::CONFIGURATION, 0, &mut bytes)?;
+    let configuration = ConfigurationDescriptor::parse(&bytes[..len])?;
+
+    let hub_info = if descriptor.class == class::HUB {
+        let hub_descriptor = hub::read_hub_descriptor(slot, speed)?;
+        Some(HubInfo {
+            nr_ports: hub_descriptor.nr_ports,
+            tt_think_time: ((hub_descriptor.characteristics >> 5) & 0x3) as u8,
+        })
+    } else {
+        None
+    };
+
+    let endpoints: Vec<_> = configuration
+        .interfaces
+        .iter()
+        .filter(|interface| interface.alternate_setting == 0)
+        .flat_map(|interface| interface.endpoints.iter().cloned())
+        .collect();
+    slot.configure(&endpoints, hub_info)?;
+
+    let setup = SetupPacket {
+        request_type: request_type::TYPE_STANDARD | request_type::RECIPIENT_DEVICE,
+        request: request::SET_CONFIGURATION,
+        value: configuration.value as u16,
+        index: 0,
+        length: 0,
+    };
+    transfer::control_out(slot, setup, &[])?;
+
+    // The hub depth is needed by the SuperSpeed hubs to parse the route strings.
+    if descriptor.class == class::HUB && speed.is_superspeed() {
+        hub::set_hub_depth(slot, depth)?;
+    }
+
+    Ok((descriptor, configuration))
+}
+
+fn get_descriptor(slot: &Slot, type_: u8, index: u8, buf: &mut [u8]) -> Result<usize, UsbError> {
+    let language_id = if type_ == descriptor_type::STRING {
+        LANGUAGE_ID
+    } else {
+        0
+    };
+    let setup = SetupPacket {
+        request_type: request_type::DIR_IN
+            | request_type::TYPE_STANDARD
+            | request_type::RECIPIENT_DEVICE,
+        request: request::GET_DESCRIPTOR,
+        value: ((type_ as u16) << 8) | index as u16,
+        index: language_id,
+        length: 0,
+    };
+    transfer::control_in(slot, setup, buf)
+}
+
+/// Reads a string descriptor, which is encoded in UTF-16.
+fn read_string(slot: &Slot, index: u8) -> Option<String> {
+    if index == 0 {
+        return None;
+    }
+
+    let mut buf = [0u8; 255];
+    let len = get_descriptor(slot, descriptor_type::STRING, index, &mut buf).ok()?;
+    if len < 2 || buf[1] != descriptor_type::STRING {
+        return None;
+    }
+    let len = len.min(buf[0] as usize);
+
+    let units = buf[2..len]
+        .chunks_exact(2)
+        .map(|unit| u16::from_le_bytes([unit[0], unit[1]]));
+    let string = char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect();
+    Some(string)
+}
+
+/// A pipe that polls an interrupt IN endpoint.
+pub struct InterruptPipe {
+    device: Weak<UsbDevice>,
+    endpoint: Arc<Endpoint>,
+    buffer: DmaCoherent,
+    /// The size of the data received at a time.
+    len: usize,
+    callback: Box<dyn Fn(&[u8]) + Send + Sync>,
+    is_stopped: AtomicBool,
+    weak_self: Weak<Self>,
+}
+
+impl InterruptPipe {
+    /// Stops polling the endpoint, after which the callback is no longer called.
+    pub fn stop(&self) {
+        self.is_stopped.store(true, Ordering::Relaxed);
+
+        if let Some(device) = self.device.upgrade()
+            && !device.is_disconnected()
+        {
+            let _ = device.slot.cancel(&self.endpoint);
+        }
+    }
+
+    fn submit(&self) -> Result<(), UsbError> {
+        let handler = self.weak_self.upgrade().unwrap();
+        self.endpoint
+            .submit_normal(self.buffer.daddr(), self.len, handler)
+    }
+}
+
+impl CompletionHandler for InterruptPipe {
+    fn complete(&self, result: Result<usize, UsbError>) {
+        if self.is_stopped.load(Ordering::Relaxed) {
+            return;
+        }
+
+        match result {
+            Ok(len) => {
+                let mut data = [0u8; MAX_INTERRUPT_TRANSFER_SIZE];
+                self.buffer.read_bytes(0, &mut data[..len]).unwrap();
+                (self.callback)(&data[..len]);
+
+                if !self.is_stopped.load(Ordering::Relaxed)
+                    && let Err(err) = self.submit()
+                {
+                    warn!("failed to resubmit the interrupt transfer: {:?}", err);
+                }
+            }
+            Err(UsbError::Cancelled | UsbError::Disconnected) => (),
+            Err(err) => {
+                // The halted endpoint cannot be reset in the interrupt context.
+                warn!(
+                    "the interrupt pipe of endpoint {:?} stops: {:?}",
+                    self.endpoint, err
+                );
+            }
+        }
+    }
+}
+
+impl core::fmt::Debug for InterruptPipe {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("InterruptPipe")
+            .field("endpoint", &self.endpoint)
+            .field("len", &self.len)
+            .finish_non_exhaustive()
+    }
+}
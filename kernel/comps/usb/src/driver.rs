@@ -0,0 +1,69 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{collections::vec_deque::VecDeque, sync::Arc};
+
+use aster_pci::{
+    PCI_BUS, PciDeviceId,
+    bus::{PciDevice, PciDriver},
+    common_device::PciCommonDevice,
+};
+use ostd::{bus::BusProbeError, sync::SpinLock};
+use spin::Once;
+
+pub(crate) static XHCI_PCI_DRIVER: Once<Arc<XhciPciDriver>> = Once::new();
+
+pub(crate) fn init() {
+    XHCI_PCI_DRIVER.call_once(|| Arc::new(XhciPciDriver::new()));
+    PCI_BUS
+        .lock()
+        .register_driver(XHCI_PCI_DRIVER.get().unwrap().clone());
+}
+
+/// The PCI driver that claims the xHCI controllers.
+///
+/// The claimed controllers are initialized later by the component.
+#[derive(Debug)]
+pub(crate) struct XhciPciDriver {
+    devices: SpinLock<VecDeque<PciCommonDevice>>,
+}
+
+impl XhciPciDriver {
+    fn new() -> Self {
+        Self {
+            devices: SpinLock::new(VecDeque::new()),
+        }
+    }
+
+    pub(crate) fn pop_device(&self) -> Option<PciCommonDevice> {
+        self.devices.lock().pop_front()
+    }
+}
+
+impl PciDriver for XhciPciDriver {
+    fn probe(
+        &self,
+        device: PciCommonDevice,
+    ) -> Result<Arc<dyn PciDevice>, (BusProbeError, PciCommonDevice)> {
+        // Serial bus controller, USB controller, xHCI
+        const XHCI_CLASS: (u8, u8, u8) = (0x0C, 0x03, 0x30);
+
+        let device_id = *device.device_id();
+        if (device_id.class, device_id.subclass, device_id.prog_if) != XHCI_CLASS {
+            return Err((BusProbeError::DeviceNotMatch, device));
+        }
+        self.devices.lock().push_back(device);
+
+        Ok(Arc::new(XhciPciDevice { device_id }))
+    }
+}
+
+#[derive(Debug)]
+struct XhciPciDevice {
+    device_id: PciDeviceId,
+}
+
+impl PciDevice for XhciPciDevice {
+    fn device_id(&self) -> PciDeviceId {
+        self.device_id
+    }
+}
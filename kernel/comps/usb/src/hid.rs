@@ -0,0 +1,477 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The driver of the USB keyboards and mice that support the boot protocol.
+//!
+//! The boot protocol has fixed report formats, so the report descriptors are not parsed.
+//!
+//! Reference: Device Class Definition for Human Interface Devices (HID), Version 1.11,
+//! Appendix B and HID Usage Tables, Version 1.12, Section 10.
+
+use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+
+use aster_input::{
+    event_type_codes::{EventTypes, KeyCode, KeyStatus, RelCode, SynEvent},
+    input_dev::{InputCapability, InputDevice, InputEvent, InputId, RegisteredInputDevice},
+};
+use ostd::sync::{Mutex, SpinLock};
+
+use crate::{
+    InterruptPipe, UsbDevice, UsbDriver, UsbError,
+    descriptor::{InterfaceDescriptor, TransferType, class},
+    request::request_type,
+};
+
+/// The subclass of the interfaces that support the boot protocol.
+const SUBCLASS_BOOT: u8 = 0x01;
+const PROTOCOL_KEYBOARD: u8 = 0x01;
+const PROTOCOL_MOUSE: u8 = 0x02;
+
+/// The class-specific requests.
+const REQUEST_SET_IDLE: u8 = 0x0A;
+const REQUEST_SET_PROTOCOL: u8 = 0x0B;
+/// The value of SET_PROTOCOL that selects the boot protocol.
+const BOOT_PROTOCOL: u16 = 0;
+
+/// The size of a keyboard report, which is the largest boot report.
+const REPORT_SIZE: usize = 8;
+
+/// The usage ID of a keyboard report that reports too many keys are pressed.
+const USAGE_ERROR_ROLL_OVER: u8 = 0x01;
+
+/// The modifier keys in the order of the bits of the first byte of a keyboard report.
+const MODIFIER_KEYS: [KeyCode; 8] = [
+    KeyCode::LeftCtrl,
+    KeyCode::LeftShift,
+    KeyCode::LeftAlt,
+    KeyCode::LeftMeta,
+    KeyCode::RightCtrl,
+    KeyCode::RightShift,
+    KeyCode::RightAlt,
+    KeyCode::RightMeta,
+];
+
+/// The mouse buttons in the order of the bits of the first byte of a mouse report.
+const MOUSE_BUTTONS: [KeyCode; 3] = [KeyCode::BtnLeft, KeyCode::BtnRight, KeyCode::BtnMiddle];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HidKind {
+    Keyboard,
+    Mouse,
+}
+
+/// The driver of the HID interfaces.
+pub(crate) struct HidDriver {
+    /// The bound interfaces and their states.
+    bindings: Mutex<Vec<Binding>>,
+}
+
+struct Binding {
+    device: Arc<UsbDevice>,
+    interface: u8,
+    pipe: Arc<InterruptPipe>,
+    handler: Arc<ReportHandler>,
+}
+
+impl HidDriver {
+    pub(crate) fn new() -> Self {
+        Self {
+            bindings: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl UsbDriver for HidDriver {
+    fn name(&self) -> &str {
+        "usbhid"
+    }
+
+    fn probe(
+        &self,
+        device: &Arc<UsbDevice>,
+        interface: &InterfaceDescriptor,
+    ) -> Result<(), UsbError> {
+        if interface.class != class::HID || interface.subclass != SUBCLASS_BOOT {
+            return Err(UsbError::NotSupported);
+        }
+        let kind = match interface.protocol {
+            PROTOCOL_KEYBOARD => HidKind::Keyboard,
+            PROTOCOL_MOUSE => HidKind::Mouse,
+            _ => return Err(UsbError::NotSupported),
+        };
+        let endpoint = interface
+            .endpoints
+            .iter()
+            .find(|desc| desc.transfer_type() == TransferType::Interrupt && desc.is_in())
+            .ok_or(UsbError::NotSupported)?
+            .address;
+
+        let class_request_type = request_type::TYPE_CLASS | request_type::RECIPIENT_INTERFACE;
+        device.control_out(
+            class_request_type,
+            REQUEST_SET_PROTOCOL,
+            BOOT_PROTOCOL,
+            interface.number as u16,
+            &[],
+        )?;
+        // Report only when the state changes. The request is optional for the mice, which may
+        // stall it.
+        let _ = device.control_out(
+            class_request_type,
+            REQUEST_SET_IDLE,
+            0,
+            interface.number as u16,
+            &[],
+        );
+
+        let input_device = Arc::new(UsbInputDevice::new(device, interface.number, kind));
+        let handler = Arc::new(ReportHandler {
+            kind,
+            state: SpinLock::new(Some(ReportState {
+                registered: aster_input::register_device(input_device),
+                last_report: [0; REPORT_SIZE],
+            })),
+        });
+
+        let callback = {
+            let handler = handler.clone();
+            Box::new(move |report: &[u8]| handler.handle_report(report))
+        };
+        let pipe = match device.open_interrupt_pipe(endpoint, callback) {
+            Ok(pipe) => pipe,
+            Err(err) => {
+                handler.unregister();
+                return Err(err);
+            }
+        };
+
+        self.bindings.lock().push(Binding {
+            device: device.clone(),
+            interface: interface.number,
+            pipe,
+            handler,
+        });
+
+        Ok(())
+    }
+
+    fn disconnect(&self, device: &Arc<UsbDevice>, interface: &InterfaceDescriptor) {
+        let mut bindings = self.bindings.lock();
+        let Some(pos) = bindings.iter().position(|binding| {
+            Arc::ptr_eq(&binding.device, device) && binding.interface == interface.number
+        }) else {
+            return;
+        };
+        let binding = bindings.swap_remove(pos);
+        drop(bindings);
+
+        binding.pipe.stop();
+        binding.handler.unregister();
+    }
+}
+
+/// The handler of the reports, which is called in the interrupt context.
+struct ReportHandler {
+    kind: HidKind,
+    /// The state, which is `None` after the device is unregistered.
+    state: SpinLock<Option<ReportState>>,
+}
+
+struct ReportState {
+    registered: RegisteredInputDevice,
+    last_report: [u8; REPORT_SIZE],
+}
+
+impl ReportHandler {
+    fn handle_report(&self, report: &[u8]) {
+        let mut state_guard = self.state.lock();
+        let Some(state) = state_guard.as_mut() else {
+            return;
+        };
+
+        let mut new_report = [0; REPORT_SIZE];
+        let len = report.len().min(REPORT_SIZE);
+        new_report[..len].copy_from_slice(&report[..len]);
+
+        let mut events = Vec::new();
+        match self.kind {
+            HidKind::Keyboard => {
+                if new_report[2..].contains(&USAGE_ERROR_ROLL_OVER) {
+                    return;
+                }
+                keyboard_events(&state.last_report, &new_report, &mut events);
+            }
+            HidKind::Mouse => mouse_events(&state.last_report, &new_report, &mut events),
+        }
+        state.last_report = new_report;
+
+        if !events.is_empty() {
+            events.push(InputEvent::from_sync_event(SynEvent::Report));
+            state.registered.submit_events(&events);
+        }
+    }
+
+    /// Unregisters the input device, after which the reports are ignored.
+    fn unregister(&self) {
+        let state = self.state.disable_irq().lock().take();
+        // The device is unregistered here, which cannot be done in the interrupt context.
+        drop(state);
+    }
+}
+
+/// Generates the events of the keys that are pressed or released.
+fn keyboard_events(old: &[u8; REPORT_SIZE], new: &[u8; REPORT_SIZE], events: &mut Vec<InputEvent>) {
+    let key_event = |key_code, is_pressed| {
+        let status = if is_pressed {
+            KeyStatus::Pressed
+        } else {
+            KeyStatus::Released
+        };
+        InputEvent::from_key_and_status(key_code, status)
+    };
+
+    let changed_modifiers = old[0] ^ new[0];
+    for (bit, key_code) in MODIFIER_KEYS.iter().enumerate() {
+        if changed_modifiers & (1 << bit) != 0 {
+            events.push(key_event(*key_code, new[0] & (1 << bit) != 0));
+        }
+    }
+
+    let (old_keys, new_keys) = (&old[2..], &new[2..]);
+    for usage in old_keys {
+        if !new_keys.contains(usage)
+            && let Some(key_code) = map_to_key_code(*usage)
+        {
+            events.push(key_event(key_code, false));
+        }
+    }
+    for usage in new_keys {
+        if !old_keys.contains(usage)
+            && let Some(key_code) = map_to_key_code(*usage)
+        {
+            events.push(key_event(key_code, true));
+        }
+    }
+}
+
+/// Generates the events of the buttons and the movements.
+///
+/// The wheel is reported in the optional fourth byte by many mice, even in the boot protocol.
+fn mouse_events(old: &[u8; REPORT_SIZE], new: &[u8; REPORT_SIZE], events: &mut Vec<InputEvent>) {
+    let changed_buttons = old[0] ^ new[0];
+    for (bit, key_code) in MOUSE_BUTTONS.iter().enumerate() {
+        if changed_buttons & (1 << bit) != 0 {
+            let status = if new[0] & (1 << bit) != 0 {
+                KeyStatus::Pressed
+            } else {
+                KeyStatus::Released
+            };
+            events.push(InputEvent::from_key_and_status(*key_code, status));
+        }
+    }
+
+    for (rel_code, value) in [
+        (RelCode::X, new[1] as i8),
+        (RelCode::Y, new[2] as i8),
+        (RelCode::Wheel, new[3] as i8),
+    ] {
+        if value != 0 {
+            events.push(InputEvent::from_relative_move(rel_code, value as i32));
+        }
+    }
+}
+
+/// An input device of a USB HID interface.
+#[derive(Debug)]
+struct UsbInputDevice {
+    name: String,
+    phys: String,
+    uniq: String,
+    id: InputId,
+    capability: InputCapability,
+}
+
+impl UsbInputDevice {
+    fn new(device: &UsbDevice, interface: u8, kind: HidKind) -> Self {
+        let mut capability = InputCapability::new();
+        capability.set_supported_event_type(EventTypes::KEY);
+        capability.set_supported_event_type(EventTypes::SYN);
+        match kind {
+            HidKind::Keyboard => {
+                for key_code in MODIFIER_KEYS {
+                    capability.set_supported_key(key_code);
+                }
+                for key_code in (0..=u8::MAX).filter_map(map_to_key_code) {
+                    capability.set_supported_key(key_code);
+                }
+            }
+            HidKind::Mouse => {
+                capability.set_supported_event_type(EventTypes::REL);
+                for key_code in MOUSE_BUTTONS {
+                    capability.set_supported_key(key_code);
+                }
+                capability.set_supported_relative_axis(RelCode::X);
+                capability.set_supported_relative_axis(RelCode::Y);
+                capability.set_supported_relative_axis(RelCode::Wheel);
+            }
+        }
+
+        // Like Linux, the name consists of the manufacturer and the product if they are known.
+        let name = match (device.manufacturer(), device.product()) {
+            (Some(manufacturer), Some(product)) => format!("{} {}", manufacturer, product),
+            (None, Some(product)) => product.to_string(),
+            _ => match kind {
+                HidKind::Keyboard => "USB Keyboard".to_string(),
+                HidKind::Mouse => "USB Mouse".to_string(),
+            },
+        };
+        let descriptor = device.descriptor();
+
+        Self {
+            name,
+            phys: format!("usb-{}/input{}", device.name(), interface),
+            uniq: device.serial_number().unwrap_or("").to_string(),
+            id: InputId::new(
+                InputId::BUS_USB,
+                descriptor.vendor_id,
+                descriptor.product_id,
+                descriptor.device_version,
+            ),
+            capability,
+        }
+    }
+}
+
+impl InputDevice for UsbInputDevice {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn phys(&self) -> &str {
+        &self.phys
+    }
+
+    fn uniq(&self) -> &str {
+        &self.uniq
+    }
+
+    fn id(&self) -> InputId {
+        self.id
+    }
+
+    fn capability(&self) -> &InputCapability {
+        &self.capability
+    }
+}
+
+/// Maps a usage ID of the keyboard page to a [`KeyCode`].
+fn map_to_key_code(usage: u8) -> Option<KeyCode> {
+    Some(match usage {
+        0x04 => KeyCode::A,
+        0x05 => KeyCode::B,
+        0x06 => KeyCode::C,
+        0x07 => KeyCode::D,
+        0x08 => KeyCode::E,
+        0x09 => KeyCode::F,
+        0x0A => KeyCode::G,
+        0x0B => KeyCode::H,
+        0x0C => KeyCode::I,
+        0x0D => KeyCode::J,
+        0x0E => KeyCode::K,
+        0x0F => KeyCode::L,
+        0x10 => KeyCode::M,
+        0x11 => KeyCode::N,
+        0x12 => KeyCode::O,
+        0x13 => KeyCode::P,
+        0x14 => KeyCode::Q,
+        0x15 => KeyCode::R,
+        0x16 => KeyCode::S,
+        0x17 => KeyCode::T,
+        0x18 => KeyCode::U,
+        0x19 => KeyCode::V,
+        0x1A => KeyCode::W,
+        0x1B => KeyCode::X,
+        0x1C => KeyCode::Y,
+        0x1D => KeyCode::Z,
+        0x1E => KeyCode::Num1,
+        0x1F => KeyCode::Num2,
+        0x20 => KeyCode::Num3,
+        0x21 => KeyCode::Num4,
+        0x22 => KeyCode::Num5,
+        0x23 => KeyCode::Num6,
+        0x24 => KeyCode::Num7,
+        0x25 => KeyCode::Num8,
+        0x26 => KeyCode::Num9,
+        0x27 => KeyCode::Num0,
+        0x28 => KeyCode::Enter,
+        0x29 => KeyCode::Esc,
+        0x2A => KeyCode::Backspace,
+        0x2B => KeyCode::Tab,
+        0x2C => KeyCode::Space,
+        0x2D => KeyCode::Minus,
+        0x2E => KeyCode::Equal,
+        0x2F => KeyCode::LeftBrace,
+        0x30 => KeyCode::RightBrace,
+        // The non-US `#` key is at the same position as the backslash key.
+        0x31 | 0x32 => KeyCode::Backslash,
+        0x33 => KeyCode::Semicolon,
+        0x34 => KeyCode::Apostrophe,
+        0x35 => KeyCode::Grave,
+        0x36 => KeyCode::Comma,
+        0x37 => KeyCode::Dot,
+        0x38 => KeyCode::Slash,
+        0x39 => KeyCode::CapsLock,
+        0x3A => KeyCode::F1,
+        0x3B => KeyCode::F2,
+        0x3C => KeyCode::F3,
+        0x3D => KeyCode::F4,
+        0x3E => KeyCode::F5,
+        0x3F => KeyCode::F6,
+        0x40 => KeyCode::F7,
+        0x41 => KeyCode::F8,
+        0x42 => KeyCode::F9,
+        0x43 => KeyCode::F10,
+        0x44 => KeyCode::F11,
+        0x45 => KeyCode::F12,
+        0x47 => KeyCode::ScrollLock,
+        0x48 => KeyCode::Pause,
+        0x49 => KeyCode::Insert,
+        0x4A => KeyCode::Home,
+        0x4B => KeyCode::PageUp,
+        0x4C => KeyCode::Delete,
+        0x4D => KeyCode::End,
+        0x4E => KeyCode::PageDown,
+        0x4F => KeyCode::Right,
+        0x50 => KeyCode::Left,
+        0x51 => KeyCode::Down,
+        0x52 => KeyCode::Up,
+        0x53 => KeyCode::NumLock,
+        0x54 => KeyCode::KpSlash,
+        0x55 => KeyCode::KpAsterisk,
+        0x56 => KeyCode::KpMinus,
+        0x57 => KeyCode::KpPlus,
+        0x58 => KeyCode::KpEnter,
+        0x59 => KeyCode::Kp1,
+        0x5A => KeyCode::Kp2,
+        0x5B => KeyCode::Kp3,
+        0x5C => KeyCode::Kp4,
+        0x5D => KeyCode::Kp5,
+        0x5E => KeyCode::Kp6,
+        0x5F => KeyCode::Kp7,
+        0x60 => KeyCode::Kp8,
+        0x61 => KeyCode::Kp9,
+        0x62 => KeyCode::Kp0,
+        0x63 => KeyCode::KpDot,
+        0x65 => KeyCode::Menu,
+        0x66 => KeyCode::Power,
+        0x7F => KeyCode::Mute,
+        0x80 => KeyCode::VolumeUp,
+        0x81 => KeyCode::VolumeDown,
+        _ => return None,
+    })
+}
@@ -0,0 +1,525 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The root hubs and the external hubs, whose ports have the devices attached.
+//!
+//! Reference: Universal Serial Bus Specification, Revision 2.0, Chapter 11, and Universal
+//! Serial Bus 3.2 Specification, Chapter 10.
+
+use alloc::{
+    boxed::Box,
+    collections::{btree_map::BTreeMap, vec_deque::VecDeque},
+    format,
+    string::String,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::time::Duration;
+
+use log::{info, warn};
+use ostd::sync::{Mutex, SpinLock, WaitQueue};
+
+use crate::{
+    UsbError, UsbSpeed, bus, delay,
+    descriptor::{TransferType, class},
+    device::UsbDevice,
+    request::{SetupPacket, request, request_type},
+    transfer,
+    xhci::{DevicePath, PortStatus, Slot, XhciController},
+};
+
+/// The descriptor types of the hub descriptors.
+const HUB_DESCRIPTOR: u8 = 0x29;
+const SS_HUB_DESCRIPTOR: u8 = 0x2A;
+
+/// The hub class request that sets the depth of a SuperSpeed hub.
+const SET_HUB_DEPTH: u8 = 12;
+
+/// The port features.
+mod port_feature {
+    pub(super) const RESET: u16 = 4;
+    pub(super) const POWER: u16 = 8;
+}
+
+/// The bits of the port status.
+mod port_status {
+    pub(super) const CONNECTION: u16 = 1 << 0;
+    pub(super) const ENABLE: u16 = 1 << 1;
+    pub(super) const LOW_SPEED: u16 = 1 << 9;
+    pub(super) const HIGH_SPEED: u16 = 1 << 10;
+}
+
+/// The bits of the port status changes.
+mod port_change {
+    pub(super) const CONNECTION: u16 = 1 << 0;
+    pub(super) const RESET: u16 = 1 << 4;
+    pub(super) const BH_RESET: u16 = 1 << 5;
+}
+
+/// The time for the connection to become stable before the port is reset.
+const DEBOUNCE_DURATION: Duration = Duration::from_millis(100);
+/// The time for the device to recover after the port is reset.
+const RESET_RECOVERY_DURATION: Duration = Duration::from_millis(10);
+/// The timeout of resetting a port of a hub.
+const RESET_TIMEOUT: Duration = Duration::from_millis(500);
+/// The minimum time for the power of the ports of a hub to become good.
+const MIN_POWER_ON_DURATION: Duration = Duration::from_millis(100);
+
+/// The maximum depth of the hubs, beyond which the route strings cannot describe the devices.
+const MAX_HUB_DEPTH: u8 = 5;
+
+/// A hub whose ports may have changed.
+enum HubEvent {
+    Root(u32),
+    External(Weak<UsbDevice>),
+}
+
+static PENDING_EVENTS: SpinLock<VecDeque<HubEvent>> = SpinLock::new(VecDeque::new());
+static WAIT_QUEUE: WaitQueue = WaitQueue::new();
+
+/// The root hubs, indexed by the indexes of the controllers.
+static ROOT_HUBS: SpinLock<Vec<Arc<RootHub>>> = SpinLock::new(Vec::new());
+
+/// Handles the next event of the hubs, which may attach or detach the devices.
+///
+/// This function should be called repeatedly by a dedicated thread. The thread may wait for the
+/// devices for seconds.
+pub fn handle_hub_events() {
+    let event = WAIT_QUEUE.wait_until(|| PENDING_EVENTS.disable_irq().lock().pop_front());
+
+    match event {
+        HubEvent::Root(index) => {
+            let root_hub = ROOT_HUBS.lock().get(index as usize).cloned();
+            if let Some(root_hub) = root_hub {
+                scan_ports(root_hub.as_ref());
+            }
+        }
+        HubEvent::External(device) => {
+            if let Some(device) = device.upgrade()
+                && !device.is_disconnected()
+                && let Some(hub) = device.hub()
+            {
+                scan_ports(hub.as_ref());
+            }
+        }
+    }
+}
+
+pub(crate) fn add_root_hub(controller: Arc<XhciController>) {
+    let index = controller.index();
+    ROOT_HUBS.lock().push(Arc::new(RootHub {
+        controller,
+        children: Mutex::new(BTreeMap::new()),
+    }));
+
+    // The devices attached before the controller is initialized have no events.
+    notify_root_hub(index);
+}
+
+/// Notifies the hub thread that the ports of a root hub may have changed.
+///
+/// This function is called in the interrupt context.
+pub(crate) fn notify_root_hub(index: u32) {
+    PENDING_EVENTS
+        .disable_irq()
+        .lock()
+        .push_back(HubEvent::Root(index));
+    WAIT_QUEUE.wake_all();
+}
+
+fn notify_hub(device: Weak<UsbDevice>) {
+    PENDING_EVENTS
+        .disable_irq()
+        .lock()
+        .push_back(HubEvent::External(device));
+    WAIT_QUEUE.wake_all();
+}
+
+/// The operations of the ports of a hub.
+trait Ports {
+    fn controller(&self) -> &Arc<XhciController>;
+
+    fn nr_ports(&self) -> u8;
+
+    fn children(&self) -> &Mutex<BTreeMap<u8, Arc<UsbDevice>>>;
+
+    /// Returns the status of a port, clearing the changes.
+    fn port_status(&self, port: u8) -> Result<PortStatus, UsbError>;
+
+    /// Resets a port, returning the speed of the device if the port is enabled.
+    fn reset_port(&self, port: u8) -> Result<Option<UsbSpeed>, UsbError>;
+
+    /// Returns the name, the path, and the depth of the device attached to a port.
+    fn child_location(
+        &self,
+        port: u8,
+        speed: UsbSpeed,
+    ) -> Result<(String, DevicePath, u8), UsbError>;
+}
+
+/// Attaches or detaches the devices according to the status of the ports.
+fn scan_ports(hub: &dyn Ports) {
+    for port in 1..=hub.nr_ports() {
+        let status = match hub.port_status(port) {
+            Ok(status) => status,
+            Err(err) => {
+                warn!(
+                    "failed to get the status of USB hub port {}: {:?}",
+                    port, err
+                );
+                continue;
+            }
+        };
+
+        let mut children = hub.children().lock();
+        if children.contains_key(&port) && (!status.is_connected || status.has_connect_change) {
+            let device = children.remove(&port).unwrap();
+            disconnect(&device);
+        }
+        if !status.is_connected || children.contains_key(&port) {
+            continue;
+        }
+
+        match attach(hub, port) {
+            Ok(device) => {
+                children.insert(port, device.clone());
+                drop(children);
+                bus::add_device(&device);
+            }
+            Err(err) => warn!(
+                "failed to enumerate the USB device at port {}: {:?}",
+                port, err
+            ),
+        }
+    }
+}
+
+fn attach(hub: &dyn Ports, port: u8) -> Result<Arc<UsbDevice>, UsbError> {
+    delay(DEBOUNCE_DURATION);
+    let speed = hub.reset_port(port)?.ok_or(UsbError::Disconnected)?;
+    delay(RESET_RECOVERY_DURATION);
+
+    let (name, path, depth) = hub.child_location(port, speed)?;
+    let device = UsbDevice::enumerate(hub.controller(), name, path, depth, speed)?;
+    let descriptor = device.descriptor();
+    info!(
+        "USB device {}: {:04x}:{:04x}, {:?} speed, {} {}",
+        device.name(),
+        descriptor.vendor_id,
+        descriptor.product_id,
+        speed,
+        device.manufacturer().unwrap_or(""),
+        device.product().unwrap_or("")
+    );
+
+    if descriptor.class == class::HUB
+        && let Err(err) = Hub::init(&device, hub.controller())
+    {
+        warn!("failed to initialize USB hub {}: {:?}", device.name(), err);
+    }
+
+    Ok(device)
+}
+
+fn disconnect(device: &Arc<UsbDevice>) {
+    // The transfers fail before the drivers are notified, so that the drivers are not blocked.
+    device.disconnect();
+
+    if let Some(hub) = device.hub() {
+        let children = core::mem::take(&mut *hub.children.lock());
+        for child in children.values() {
+            disconnect(child);
+        }
+    }
+
+    bus::remove_device(device);
+    info!("USB device {} is disconnected", device.name());
+}
+
+/// The root hub of a controller.
+struct RootHub {
+    controller: Arc<XhciController>,
+    children: Mutex<BTreeMap<u8, Arc<UsbDevice>>>,
+}
+
+impl Ports for RootHub {
+    fn controller(&self) -> &Arc<XhciController> {
+        &self.controller
+    }
+
+    fn nr_ports(&self) -> u8 {
+        self.controller.nr_ports()
+    }
+
+    fn children(&self) -> &Mutex<BTreeMap<u8, Arc<UsbDevice>>> {
+        &self.children
+    }
+
+    fn port_status(&self, port: u8) -> Result<PortStatus, UsbError> {
+        Ok(self.controller.port_status(port))
+    }
+
+    fn reset_port(&self, port: u8) -> Result<Option<UsbSpeed>, UsbError> {
+        Ok(self.controller.reset_port(port))
+    }
+
+    fn child_location(
+        &self,
+        port: u8,
+        _speed: UsbSpeed,
+    ) -> Result<(String, DevicePath, u8), UsbError> {
+        let name = format!("{}-{}", self.controller.index() + 1, port);
+        let path = DevicePath {
+            root_port: port,
+            route: 0,
+            tt: None,
+        };
+        Ok((name, path, 0))
+    }
+}
+
+/// The hub descriptor, which is parsed partially.
+pub(crate) struct HubDescriptor {
+    pub(crate) nr_ports: u8,
+    pub(crate) characteristics: u16,
+    /// The time for the power of a port to become good in 2-millisecond units.
+    pub(crate) power_on_delay: u8,
+}
+
+pub(crate) fn read_hub_descriptor(slot: &Slot, speed: UsbSpeed) -> Result<HubDescriptor, UsbError> {
+    let type_ = if speed.is_superspeed() {
+        SS_HUB_DESCRIPTOR
+    } else {
+        HUB_DESCRIPTOR
+    };
+    let setup = SetupPacket {
+        request_type: request_type::DIR_IN
+            | request_type::TYPE_CLASS
+            | request_type::RECIPIENT_DEVICE,
+        request: request::GET_DESCRIPTOR,
+        value: (type_ as u16) << 8,
+        index: 0,
+        length: 0,
+    };
+
+    let mut buf = [0u8; 12];
+    let len = transfer::control_in(slot, setup, &mut buf)?;
+    if len < 7 || buf[1] != type_ {
+        return Err(UsbError::NotSupported);
+    }
+
+    Ok(HubDescriptor {
+        nr_ports: buf[2],
+        characteristics: u16::from_le_bytes([buf[3], buf[4]]),
+        power_on_delay: buf[5],
+    })
+}
+
+/// Sets the depth of a SuperSpeed hub, where the hubs attached to the root hub ports have
+/// depth 0.
+pub(crate) fn set_hub_depth(slot: &Slot, depth: u8) -> Result<(), UsbError> {
+    let setup = SetupPacket {
+        request_type: request_type::TYPE_CLASS | request_type::RECIPIENT_DEVICE,
+        request: SET_HUB_DEPTH,
+        value: depth as u16,
+        index: 0,
+        length: 0,
+    };
+    transfer::control_out(slot, setup, &[])
+}
+
+/// An external hub.
+pub(crate) struct Hub {
+    device: Weak<UsbDevice>,
+    controller: Arc<XhciController>,
+    nr_ports: u8,
+    children: Mutex<BTreeMap<u8, Arc<UsbDevice>>>,
+}
+
+impl Hub {
+    /// Powers the ports of a hub, and starts polling the status changes.
+    fn init(device: &Arc<UsbDevice>, controller: &Arc<XhciController>) -> Result<(), UsbError> {
+        if device.depth() >= MAX_HUB_DEPTH {
+            return Err(UsbError::NotSupported);
+        }
+
+        let descriptor = read_hub_descriptor(device.slot(), device.speed())?;
+        let hub = Arc::new(Self {
+            device: Arc::downgrade(device),
+            controller: controller.clone(),
+            nr_ports: descriptor.nr_ports,
+            children: Mutex::new(BTreeMap::new()),
+        });
+
+        for port in 1..=hub.nr_ports {
+            hub.set_port_feature(device, port, port_feature::POWER)?;
+        }
+        let power_on_duration = Duration::from_millis(descriptor.power_on_delay as u64 * 2);
+        delay(power_on_duration.max(MIN_POWER_ON_DURATION));
+
+        // The hub reports the changed ports with a bitmap, but all the ports are scanned.
+        let status_endpoint = device
+            .configuration()
+            .interfaces
+            .iter()
+            .filter(|interface| interface.alternate_setting == 0)
+            .flat_map(|interface| interface.endpoints.iter())
+            .find(|endpoint| {
+                endpoint.transfer_type() == TransferType::Interrupt && endpoint.is_in()
+            })
+            .ok_or(UsbError::NotSupported)?
+            .address;
+        let weak_device = Arc::downgrade(device);
+        device.open_interrupt_pipe(
+            status_endpoint,
+            Box::new(move |_| notify_hub(weak_device.clone())),
+        )?;
+
+        device.set_hub(hub);
+        // The devices attached before the hub is powered have no events.
+        notify_hub(Arc::downgrade(device));
+
+        Ok(())
+    }
+
+    fn device(&self) -> Result<Arc<UsbDevice>, UsbError> {
+        self.device.upgrade().ok_or(UsbError::Disconnected)
+    }
+
+    fn get_port_status(&self, device: &UsbDevice, port: u8) -> Result<(u16, u16), UsbError> {
+        let mut buf = [0u8; 4];
+        let len = device.control_in(
+            request_type::TYPE_CLASS | request_type::RECIPIENT_OTHER,
+            request::GET_STATUS,
+            0,
+            port as u16,
+            &mut buf,
+        )?;
+        if len < buf.len() {
+            return Err(UsbError::NotSupported);
+        }
+
+        let status = u16::from_le_bytes([buf[0], buf[1]]);
+        let change = u16::from_le_bytes([buf[2], buf[3]]);
+        Ok((status, change))
+    }
+
+    fn set_port_feature(&self, device: &UsbDevice, port: u8, feature: u16) -> Result<(), UsbError> {
+        device.control_out(
+            request_type::TYPE_CLASS | request_type::RECIPIENT_OTHER,
+            request::SET_FEATURE,
+            feature,
+            port as u16,
+            &[],
+        )
+    }
+
+    /// Clears the changes of a port.
+    fn clear_port_changes(
+        &self,
+        device: &UsbDevice,
+        port: u8,
+        change: u16,
+    ) -> Result<(), UsbError> {
+        /// The features that clear the change bits, indexed by the bits.
+        const CHANGE_FEATURES: [u16; 8] = [16, 17, 18, 19, 20, 29, 25, 26];
+
+        for (bit, feature) in CHANGE_FEATURES.iter().enumerate() {
+            if change & (1 << bit) != 0 {
+                device.control_out(
+                    request_type::TYPE_CLASS | request_type::RECIPIENT_OTHER,
+                    request::CLEAR_FEATURE,
+                    *feature,
+                    port as u16,
+                    &[],
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Ports for Hub {
+    fn controller(&self) -> &Arc<XhciController> {
+        &self.controller
+    }
+
+    fn nr_ports(&self) -> u8 {
+        self.nr_ports
+    }
+
+    fn children(&self) -> &Mutex<BTreeMap<u8, Arc<UsbDevice>>> {
+        &self.children
+    }
+
+    fn port_status(&self, port: u8) -> Result<PortStatus, UsbError> {
+        let device = self.device()?;
+        let (status, change) = self.get_port_status(&device, port)?;
+        self.clear_port_changes(&device, port, change)?;
+
+        Ok(PortStatus {
+            is_connected: status & port_status::CONNECTION != 0,
+            has_connect_change: change & port_change::CONNECTION != 0,
+        })
+    }
+
+    fn reset_port(&self, port: u8) -> Result<Option<UsbSpeed>, UsbError> {
+        let device = self.device()?;
+        self.set_port_feature(&device, port, port_feature::RESET)?;
+
+        let resets = port_change::RESET | port_change::BH_RESET;
+        let mut remaining = RESET_TIMEOUT;
+        let (status, change) = loop {
+            delay(RESET_RECOVERY_DURATION);
+            let (status, change) = self.get_port_status(&device, port)?;
+            if change & resets != 0 {
+                break (status, change);
+            }
+            remaining = remaining.saturating_sub(RESET_RECOVERY_DURATION);
+            if remaining.is_zero() {
+                return Err(UsbError::Timeout);
+            }
+        };
+        self.clear_port_changes(&device, port, change & resets)?;
+
+        if status & port_status::ENABLE == 0 {
+            return Ok(None);
+        }
+        let speed = if device.speed().is_superspeed() {
+            device.speed()
+        } else if status & port_status::LOW_SPEED != 0 {
+            UsbSpeed::Low
+        } else if status & port_status::HIGH_SPEED != 0 {
+            UsbSpeed::High
+        } else {
+            UsbSpeed::Full
+        };
+        Ok(Some(speed))
+    }
+
+    fn child_location(
+        &self,
+        port: u8,
+        speed: UsbSpeed,
+    ) -> Result<(String, DevicePath, u8), UsbError> {
+        let device = self.device()?;
+        let parent_path = device.path();
+
+        let name = format!("{}.{}", device.name(), port);
+        // The transaction translator of the nearest high-speed hub serves the low-speed and
+        // full-speed devices.
+        let tt = if speed > UsbSpeed::Full {
+            None
+        } else if device.speed() == UsbSpeed::High {
+            Some((device.slot_id(), port))
+        } else {
+            parent_path.tt
+        };
+        let path = DevicePath {
+            root_port: parent_path.root_port,
+            route: parent_path.route | ((port.min(15) as u32) << (4 * device.depth())),
+            tt,
+        };
+        Ok((name, path, device.depth() + 1))
+    }
+}
@@ -0,0 +1,165 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The USB subsystem of Asterinas.
+//!
+//! The subsystem claims the xHCI controllers on the PCI bus, whose root hub ports form the USB
+//! buses. The devices attached to the buses, directly or through the hubs, are enumerated by
+//! [`handle_hub_events`], which should be called by a dedicated thread. Each interface of a
+//! device is bound to the first registered [`UsbDriver`] that accepts it.
+//!
+//! The subsystem has built-in drivers for the mass storage devices, whose disks are reported by
+//! [`wait_for_disk_event`], and for the keyboards and mice, which feed the input subsystem.
+//!
+//! The devices are named like Linux, e.g., `1-2.3` is the device attached to port 3 of the hub
+//! attached to port 2 of the root hub of bus 1.
+//!
+//! Reference: Universal Serial Bus Specification, Revision 2.0.
+
+#![no_std]
+#![deny(unsafe_code)]
+
+extern crate alloc;
+#[macro_use]
+extern crate ostd_pod;
+
+mod bus;
+pub mod descriptor;
+mod device;
+mod driver;
+mod hid;
+mod hub;
+pub mod request;
+mod storage;
+mod transfer;
+mod xhci;
+
+use alloc::sync::Arc;
+use core::{hint::spin_loop, time::Duration};
+
+use component::{ComponentInitError, init_component};
+use log::error;
+use ostd::timer::Jiffies;
+
+pub use self::{
+    bus::{UsbDriver, all_devices, register_driver},
+    device::{InterruptPipe, UsbDevice},
+    hub::handle_hub_events,
+    storage::{DiskEvent, UsbDisk, wait_for_disk_event},
+};
+use self::{driver::XHCI_PCI_DRIVER, hid::HidDriver, storage::StorageDriver, xhci::XhciController};
+
+/// An error that occurs when transferring data or enumerating a device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsbError {
+    /// The endpoint stalls, e.g., because the request is not supported.
+    Stall,
+    /// The device sends more data than expected.
+    Babble,
+    /// The transaction fails after the retries, e.g., due to the CRC errors.
+    TransactionError,
+    /// The transfer does not complete in time.
+    Timeout,
+    /// The transfer is cancelled.
+    Cancelled,
+    /// The device is disconnected.
+    Disconnected,
+    /// The argument is invalid, e.g., the endpoint does not exist.
+    InvalidArgument,
+    /// The device or the request is not supported.
+    NotSupported,
+    /// The host controller runs out of resources, e.g., the device slots.
+    NoResources,
+    /// The host controller reports an error with the completion code.
+    HostError(u8),
+}
+
+/// The speed of a device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UsbSpeed {
+    /// 1.5 Mb/s.
+    Low,
+    /// 12 Mb/s.
+    Full,
+    /// 480 Mb/s.
+    High,
+    /// 5 Gb/s.
+    Super,
+    /// 10 Gb/s or more.
+    SuperPlus,
+}
+
+impl UsbSpeed {
+    /// Returns the default protocol speed ID (PSI) of the xHCI controllers.
+    pub(crate) fn protocol_speed_id(self) -> u8 {
+        match self {
+            Self::Full => 1,
+            Self::Low => 2,
+            Self::High => 3,
+            Self::Super => 4,
+            Self::SuperPlus => 5,
+        }
+    }
+
+    pub(crate) fn from_protocol_speed_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(Self::Full),
+            2 => Some(Self::Low),
+            3 => Some(Self::High),
+            4 => Some(Self::Super),
+            5 => Some(Self::SuperPlus),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the speed is a SuperSpeed one, i.e., the device is a USB 3 device.
+    pub fn is_superspeed(self) -> bool {
+        self >= Self::Super
+    }
+}
+
+#[init_component]
+fn usb_init() -> Result<(), ComponentInitError> {
+    driver::init();
+    register_driver(Arc::new(StorageDriver::new()));
+    register_driver(Arc::new(HidDriver::new()));
+
+    let mut index = 0;
+    while let Some(device) = XHCI_PCI_DRIVER.get().unwrap().pop_device() {
+        let controller = match XhciController::init(&device, index) {
+            Ok(controller) => controller,
+            Err(err) => {
+                error!(
+                    "failed to initialize the xHCI controller at {:?}: {:?}",
+                    device.location(),
+                    err
+                );
+                continue;
+            }
+        };
+        index += 1;
+
+        hub::add_root_hub(controller);
+    }
+
+    Ok(())
+}
+
+/// Waits until the condition holds, returning whether it holds before the timeout.
+pub(crate) fn wait_for(mut cond: impl FnMut() -> bool, timeout: Duration) -> bool {
+    let deadline = Jiffies::elapsed().as_duration() + timeout;
+
+    loop {
+        if cond() {
+            return true;
+        }
+        if Jiffies::elapsed().as_duration() > deadline {
+            return false;
+        }
+        spin_loop();
+    }
+}
+
+/// Busy-waits for the duration, e.g., for the devices to settle.
+pub(crate) fn delay(duration: Duration) {
+    wait_for(|| false, duration);
+}
@@ -0,0 +1,56 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The control requests.
+//!
+//! Reference: Universal Serial Bus Specification, Revision 2.0, Section 9.3 and Section 9.4.
+
+/// The bits of the `bmRequestType` field.
+pub mod request_type {
+    /// The data stage transfers data from the device to the host.
+    pub const DIR_IN: u8 = 1 << 7;
+
+    pub const TYPE_STANDARD: u8 = 0 << 5;
+    pub const TYPE_CLASS: u8 = 1 << 5;
+    pub const TYPE_VENDOR: u8 = 2 << 5;
+
+    pub const RECIPIENT_DEVICE: u8 = 0;
+    pub const RECIPIENT_INTERFACE: u8 = 1;
+    pub const RECIPIENT_ENDPOINT: u8 = 2;
+    pub const RECIPIENT_OTHER: u8 = 3;
+}
+
+/// The standard requests.
+pub mod request {
+    pub const GET_STATUS: u8 = 0;
+    pub const CLEAR_FEATURE: u8 = 1;
+    pub const SET_FEATURE: u8 = 3;
+    pub const SET_ADDRESS: u8 = 5;
+    pub const GET_DESCRIPTOR: u8 = 6;
+    pub const SET_DESCRIPTOR: u8 = 7;
+    pub const GET_CONFIGURATION: u8 = 8;
+    pub const SET_CONFIGURATION: u8 = 9;
+    pub const GET_INTERFACE: u8 = 10;
+    pub const SET_INTERFACE: u8 = 11;
+}
+
+/// The feature selector that halts an endpoint.
+pub const FEATURE_ENDPOINT_HALT: u16 = 0;
+
+/// A setup packet, which starts a control transfer.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub struct SetupPacket {
+    pub request_type: u8,
+    pub request: u8,
+    pub value: u16,
+    pub index: u16,
+    /// The length of the data stage.
+    pub length: u16,
+}
+
+impl SetupPacket {
+    /// Returns whether the data stage (if any) transfers data from the device to the host.
+    pub fn is_in(&self) -> bool {
+        self.request_type & request_type::DIR_IN != 0
+    }
+}
@@ -0,0 +1,107 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The SCSI commands that are used to enumerate and access the disks.
+//!
+//! Like Linux, only the 6-byte and 10-byte commands are used unless the disk is too large, since
+//! many USB flash drives do not support the others.
+//!
+//! Reference: SCSI Block Commands - 3 (SBC-3) and SCSI Primary Commands - 4 (SPC-4).
+
+/// A SCSI command descriptor block.
+#[derive(Debug)]
+pub(super) struct Cdb {
+    bytes: [u8; 16],
+    len: usize,
+}
+
+impl Cdb {
+    fn new(opcode: u8, len: usize) -> Self {
+        let mut bytes = [0; 16];
+        bytes[0] = opcode;
+        Self { bytes, len }
+    }
+
+    pub(super) fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    /// Creates a TEST UNIT READY command.
+    pub(super) fn test_unit_ready() -> Self {
+        Self::new(0x00, 6)
+    }
+
+    /// Creates a REQUEST SENSE command for the fixed format sense data.
+    pub(super) fn request_sense(alloc_len: u8) -> Self {
+        let mut cdb = Self::new(0x03, 6);
+        cdb.bytes[4] = alloc_len;
+        cdb
+    }
+
+    /// Creates an INQUIRY command for the standard inquiry data.
+    pub(super) fn inquiry(alloc_len: u8) -> Self {
+        let mut cdb = Self::new(0x12, 6);
+        cdb.bytes[4] = alloc_len;
+        cdb
+    }
+
+    /// Creates a READ CAPACITY (10) command.
+    pub(super) fn read_capacity_10() -> Self {
+        Self::new(0x25, 10)
+    }
+
+    /// Creates a READ CAPACITY (16) command.
+    pub(super) fn read_capacity_16(alloc_len: u32) -> Self {
+        const SERVICE_ACTION: u8 = 0x10;
+
+        let mut cdb = Self::new(0x9E, 16);
+        cdb.bytes[1] = SERVICE_ACTION;
+        cdb.bytes[10..14].copy_from_slice(&alloc_len.to_be_bytes());
+        cdb
+    }
+
+    /// Creates a READ (10) or READ (16) command, depending on whether the LBA fits in 32 bits.
+    pub(super) fn read(lba: u64, nr_blocks: u16) -> Self {
+        if let Ok(lba) = u32::try_from(lba) {
+            let mut cdb = Self::new(0x28, 10);
+            cdb.bytes[2..6].copy_from_slice(&lba.to_be_bytes());
+            cdb.bytes[7..9].copy_from_slice(&nr_blocks.to_be_bytes());
+            cdb
+        } else {
+            let mut cdb = Self::new(0x88, 16);
+            cdb.bytes[2..10].copy_from_slice(&lba.to_be_bytes());
+            cdb.bytes[10..14].copy_from_slice(&(nr_blocks as u32).to_be_bytes());
+            cdb
+        }
+    }
+
+    /// Creates a WRITE (10) or WRITE (16) command, depending on whether the LBA fits in 32 bits.
+    pub(super) fn write(lba: u64, nr_blocks: u16) -> Self {
+        let mut cdb = Self::read(lba, nr_blocks);
+        cdb.bytes[0] = if cdb.len == 10 { 0x2A } else { 0x8A };
+        cdb
+    }
+
+    /// Creates a SYNCHRONIZE CACHE (10) command for the whole disk.
+    pub(super) fn synchronize_cache_10() -> Self {
+        Self::new(0x35, 10)
+    }
+}
+
+/// The size of the fixed format sense data that is requested.
+pub(super) const SENSE_SIZE: usize = 18;
+
+/// The sense key that reports the medium is not present or not ready.
+pub(super) const SENSE_KEY_NOT_READY: u8 = 0x2;
+/// The sense key that reports a unit attention condition, e.g., after the medium is changed.
+pub(super) const SENSE_KEY_UNIT_ATTENTION: u8 = 0x6;
+
+/// Returns the sense key in the sense data.
+pub(super) fn sense_key(sense: &[u8]) -> Option<u8> {
+    match sense.first()? & 0x7F {
+        // Fixed format
+        0x70 | 0x71 => sense.get(2).map(|key| key & 0xF),
+        // Descriptor format
+        0x72 | 0x73 => sense.get(1).map(|key| key & 0xF),
+        _ => None,
+    }
+}
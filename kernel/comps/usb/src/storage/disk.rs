@@ -0,0 +1,303 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{
+    sync::{Arc, Weak},
+    vec,
+    vec::Vec,
+};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use aster_block::{
+    BlockDevice, BlockDeviceMeta, EXTENDED_DEVICE_ID_ALLOCATOR, PartitionInfo, PartitionNode,
+    SCSI_DISK_ALLOCATOR, SECTOR_SIZE, ScsiDiskSlot,
+    bio::{BioEnqueueError, BioStatus, BioType, SubmittedBio},
+    request_queue::{BioRequest, BioRequestQueue},
+};
+use device_id::DeviceId;
+use log::{debug, warn};
+use ostd::{mm::VmIo, sync::SpinLock};
+
+use super::{
+    command::Cdb,
+    transport::{BulkOnly, CommandError, DataStage},
+};
+use crate::{UsbDevice, UsbError};
+
+/// The maximum size of the data transferred by a command.
+///
+/// This is the same as the default of Linux, which some devices rely on.
+const MAX_TRANSFER_SIZE: usize = 120 << 10;
+
+/// The information of a SCSI disk.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct DiskInfo {
+    pub(super) lun: u8,
+    /// The size of the disk in logical blocks.
+    pub(super) nr_blocks: u64,
+    /// The size of a logical block, as a power of two.
+    pub(super) lba_shift: u32,
+}
+
+/// A disk of a USB mass storage device, e.g., a USB flash drive.
+///
+/// Like Linux, the USB disks share the names and device IDs with the SCSI disks, e.g., `sdb`.
+/// The requests of a disk should be handled by a dedicated thread (see
+/// [`UsbDisk::handle_requests`]).
+#[derive(Debug)]
+pub struct UsbDisk {
+    transport: Arc<BulkOnly>,
+    info: DiskInfo,
+    /// The software staging queue.
+    queue: BioRequestQueue,
+    slot: ScsiDiskSlot,
+    is_detached: AtomicBool,
+    partitions: SpinLock<Option<Vec<Arc<PartitionNode>>>>,
+    weak_self: Weak<Self>,
+}
+
+impl UsbDisk {
+    pub(super) fn new(transport: Arc<BulkOnly>, info: DiskInfo) -> Result<Arc<Self>, UsbError> {
+        let slot = SCSI_DISK_ALLOCATOR
+            .get()
+            .unwrap()
+            .allocate()
+            .map_err(|_| UsbError::NoResources)?;
+
+        Ok(Arc::new_cyclic(|weak_self| Self {
+            transport,
+            info,
+            queue: BioRequestQueue::new(),
+            slot,
+            is_detached: AtomicBool::new(false),
+            partitions: SpinLock::new(None),
+            weak_self: weak_self.clone(),
+        }))
+    }
+
+    /// Returns the USB device of the disk.
+    pub fn device(&self) -> &Arc<UsbDevice> {
+        self.transport.device()
+    }
+
+    /// Dequeues a `BioRequest` from the software staging queue and
+    /// executes the request.
+    ///
+    /// This method returns `false` without waiting if the disk is detached and all the
+    /// requests have been handled, in which case the thread should exit.
+    pub fn handle_requests(&self) -> bool {
+        let Some(request) = self.queue.dequeue_unless_closed() else {
+            return false;
+        };
+        debug!("Handle Request: {:?}", request);
+
+        if self.is_detached.load(Ordering::Relaxed) {
+            complete_request(&request, BioStatus::IoError);
+            return true;
+        }
+
+        let status = match request.type_() {
+            BioType::Read => self.read(&request),
+            BioType::Write => self.write(&request),
+            BioType::Flush => self.flush(),
+            BioType::Discard => BioStatus::NotSupported,
+        };
+        complete_request(&request, status);
+        true
+    }
+
+    fn read(&self, request: &BioRequest) -> BioStatus {
+        let Some(lba) = self.start_lba(request) else {
+            return BioStatus::IoError;
+        };
+
+        let mut data = vec![0u8; request.num_sectors() * SECTOR_SIZE];
+        if !self.transfer_blocks(lba, &mut data, false) {
+            return BioStatus::IoError;
+        }
+
+        let mut offset = 0;
+        for bio in request.bios() {
+            for segment in bio.segments() {
+                let len = segment.nbytes();
+                segment
+                    .inner_dma_slice()
+                    .write_bytes(0, &data[offset..offset + len])
+                    .unwrap();
+                offset += len;
+            }
+        }
+
+        BioStatus::Complete
+    }
+
+    fn write(&self, request: &BioRequest) -> BioStatus {
+        let Some(lba) = self.start_lba(request) else {
+            return BioStatus::IoError;
+        };
+
+        let mut data = vec![0u8; request.num_sectors() * SECTOR_SIZE];
+        let mut offset = 0;
+        for bio in request.bios() {
+            for segment in bio.segments() {
+                let len = segment.nbytes();
+                segment
+                    .inner_dma_slice()
+                    .read_bytes(0, &mut data[offset..offset + len])
+                    .unwrap();
+                offset += len;
+            }
+        }
+
+        if !self.transfer_blocks(lba, &mut data, true) {
+            return BioStatus::IoError;
+        }
+
+        BioStatus::Complete
+    }
+
+    fn flush(&self) -> BioStatus {
+        match self.execute(&Cdb::synchronize_cache_10(), DataStage::None) {
+            Ok(_) => BioStatus::Complete,
+            // Many devices do not have a write cache and do not support the command.
+            Err(CommandError::Failed(_)) => BioStatus::Complete,
+            Err(_) => BioStatus::IoError,
+        }
+    }
+
+    /// Returns the starting LBA of the request, or `None` if the request is not aligned to the
+    /// logical blocks.
+    fn start_lba(&self, request: &BioRequest) -> Option<u64> {
+        let sector_shift = self.info.lba_shift - SECTOR_SIZE.ilog2();
+        let start = request.sid_range().start.to_raw();
+        let nr_sectors = request.num_sectors() as u64;
+        if (start | nr_sectors) & ((1 << sector_shift) - 1) != 0 {
+            warn!("unaligned USB disk request: {:?}", request);
+            return None;
+        }
+
+        Some(start >> sector_shift)
+    }
+
+    /// Reads or writes the blocks starting from `lba`, returning whether it succeeds.
+    fn transfer_blocks(&self, mut lba: u64, data: &mut [u8], is_write: bool) -> bool {
+        for chunk in data.chunks_mut(MAX_TRANSFER_SIZE) {
+            let nr_blocks = (chunk.len() >> self.info.lba_shift) as u16;
+            let result = if is_write {
+                self.execute(&Cdb::write(lba, nr_blocks), DataStage::Out(chunk))
+            } else {
+                self.execute(&Cdb::read(lba, nr_blocks), DataStage::In(chunk))
+            };
+
+            match result {
+                Ok(len) if len == chunk.len() => (),
+                Ok(len) => {
+                    warn!(
+                        "USB disk {}: short transfer of {} bytes at LBA {}",
+                        self.name(),
+                        len,
+                        lba
+                    );
+                    return false;
+                }
+                Err(CommandError::Usb(UsbError::Disconnected)) => return false,
+                Err(err) => {
+                    warn!(
+                        "USB disk {}: failed to transfer at LBA {}: {:?}",
+                        self.name(),
+                        lba,
+                        err
+                    );
+                    return false;
+                }
+            }
+
+            lba += nr_blocks as u64;
+        }
+
+        true
+    }
+
+    fn execute(&self, cdb: &Cdb, data: DataStage<'_>) -> Result<usize, CommandError> {
+        self.transport.execute(self.info.lun, cdb, data)
+    }
+
+    /// Marks the disk as detached, so that the new requests fail.
+    pub(super) fn mark_detached(&self) {
+        self.is_detached.store(true, Ordering::Relaxed);
+        self.queue.close();
+    }
+}
+
+fn complete_request(request: &BioRequest, status: BioStatus) {
+    request.bios().for_each(|bio| bio.complete(status));
+}
+
+impl BlockDevice for UsbDisk {
+    fn enqueue(&self, bio: SubmittedBio) -> Result<(), BioEnqueueError> {
+        self.queue.enqueue(bio)
+    }
+
+    fn metadata(&self) -> BlockDeviceMeta {
+        BlockDeviceMeta {
+            max_nr_segments_per_bio: self.queue.max_nr_segments_per_bio(),
+            nr_sectors: (self.info.nr_blocks << self.info.lba_shift) as usize / SECTOR_SIZE,
+            supports_fua: false,
+            max_discard_sectors: 0,
+        }
+    }
+
+    fn name(&self) -> &str {
+        self.slot.name()
+    }
+
+    fn id(&self) -> DeviceId {
+        self.slot.id()
+    }
+
+    fn set_partitions(&self, infos: Vec<Option<PartitionInfo>>) {
+        let old_partitions = self.partitions.lock().take();
+        for partition in old_partitions.into_iter().flatten() {
+            let _ = aster_block::unregister(partition.id());
+            // This does nothing if the ID is not an extended device ID.
+            EXTENDED_DEVICE_ID_ALLOCATOR
+                .get()
+                .unwrap()
+                .release(partition.id());
+        }
+
+        let mut new_partitions = Vec::new();
+        for (index, info_opt) in infos.iter().enumerate() {
+            let Some(info) = info_opt else {
+                continue;
+            };
+
+            let number = index as u32 + 1;
+            let id = self.slot.allocate_partition_id(number);
+            let name = aster_block::partition_name(self.name(), number);
+            let device = self.weak_self.upgrade().unwrap();
+
+            let partition = Arc::new(PartitionNode::new(id, name, device, number, *info));
+            new_partitions.push(partition);
+        }
+
+        for partition in new_partitions.iter() {
+            let _ = aster_block::register(partition.clone());
+        }
+
+        *self.partitions.lock() = Some(new_partitions);
+    }
+
+    fn partitions(&self) -> Option<Vec<Arc<dyn BlockDevice>>> {
+        let partitions = self.partitions.lock();
+        let devices = partitions
+            .as_ref()?
+            .iter()
+            .map(|p| p.clone() as Arc<dyn BlockDevice>)
+            .collect();
+        Some(devices)
+    }
+
+    fn request_queue(&self) -> Option<&BioRequestQueue> {
+        Some(&self.queue)
+    }
+}
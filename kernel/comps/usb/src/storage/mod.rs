@@ -0,0 +1,257 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The driver of the USB mass storage devices that use the Bulk-Only Transport.
+//!
+//! Each logical unit (LUN) of a device is a [`UsbDisk`], which is reported by
+//! [`wait_for_disk_event`] to be registered as a block device.
+
+mod command;
+mod disk;
+mod transport;
+
+use alloc::{collections::vec_deque::VecDeque, string::String, sync::Arc, vec::Vec};
+use core::time::Duration;
+
+use aster_block::SECTOR_SIZE;
+use log::{info, warn};
+use ostd::{
+    mm::PAGE_SIZE,
+    sync::{Mutex, SpinLock, WaitQueue},
+};
+
+pub use self::disk::UsbDisk;
+use self::{
+    command::{Cdb, SENSE_KEY_NOT_READY, SENSE_KEY_UNIT_ATTENTION},
+    disk::DiskInfo,
+    transport::{BulkOnly, CommandError, DataStage},
+};
+use crate::{
+    UsbDevice, UsbDriver, UsbError, delay,
+    descriptor::{InterfaceDescriptor, TransferType, class},
+};
+
+/// The subclass of the devices that use the SCSI transparent command set.
+const SUBCLASS_SCSI: u8 = 0x06;
+/// The protocol of the Bulk-Only Transport.
+const PROTOCOL_BULK_ONLY: u8 = 0x50;
+
+/// The number of times to wait for a disk to become ready.
+const MAX_READY_RETRIES: usize = 10;
+const READY_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// An event that a USB disk is attached or detached.
+#[derive(Debug)]
+pub enum DiskEvent {
+    /// A disk is attached and should be registered.
+    Attached(Arc<UsbDisk>),
+    /// A disk is detached and should be unregistered.
+    Detached(Arc<UsbDisk>),
+}
+
+static PENDING_EVENTS: SpinLock<VecDeque<DiskEvent>> = SpinLock::new(VecDeque::new());
+static WAIT_QUEUE: WaitQueue = WaitQueue::new();
+
+/// Waits for the next event of the USB disks.
+///
+/// This function should be called repeatedly by a dedicated thread.
+pub fn wait_for_disk_event() -> DiskEvent {
+    WAIT_QUEUE.wait_until(|| PENDING_EVENTS.lock().pop_front())
+}
+
+fn notify(event: DiskEvent) {
+    PENDING_EVENTS.lock().push_back(event);
+    WAIT_QUEUE.wake_all();
+}
+
+/// The driver of the USB mass storage interfaces.
+pub(crate) struct StorageDriver {
+    /// The bound interfaces and their disks.
+    disks: Mutex<Vec<(Arc<UsbDevice>, u8, Vec<Arc<UsbDisk>>)>>,
+}
+
+impl StorageDriver {
+    pub(crate) fn new() -> Self {
+        Self {
+            disks: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl UsbDriver for StorageDriver {
+    fn name(&self) -> &str {
+        "usb-storage"
+    }
+
+    fn probe(
+        &self,
+        device: &Arc<UsbDevice>,
+        interface: &InterfaceDescriptor,
+    ) -> Result<(), UsbError> {
+        if interface.class != class::MASS_STORAGE
+            || interface.subclass != SUBCLASS_SCSI
+            || interface.protocol != PROTOCOL_BULK_ONLY
+        {
+            return Err(UsbError::NotSupported);
+        }
+
+        let find_endpoint = |is_in: bool| {
+            interface
+                .endpoints
+                .iter()
+                .find(|desc| desc.transfer_type() == TransferType::Bulk && desc.is_in() == is_in)
+                .map(|desc| desc.address)
+                .ok_or(UsbError::NotSupported)
+        };
+        let transport = Arc::new(BulkOnly::new(
+            device.clone(),
+            interface.number,
+            find_endpoint(true)?,
+            find_endpoint(false)?,
+        ));
+
+        let mut disks = Vec::new();
+        for lun in 0..=transport.max_lun() {
+            let Some(info) = probe_disk(&transport, lun) else {
+                continue;
+            };
+            match UsbDisk::new(transport.clone(), info) {
+                Ok(disk) => disks.push(disk),
+                Err(err) => warn!("failed to create the USB disk: {:?}", err),
+            }
+        }
+        if device.is_disconnected() {
+            return Err(UsbError::Disconnected);
+        }
+
+        for disk in disks.iter() {
+            notify(DiskEvent::Attached(disk.clone()));
+        }
+        self.disks
+            .lock()
+            .push((device.clone(), interface.number, disks));
+
+        Ok(())
+    }
+
+    fn disconnect(&self, device: &Arc<UsbDevice>, interface: &InterfaceDescriptor) {
+        let mut all_disks = self.disks.lock();
+        let Some(pos) = all_disks.iter().position(|(other, number, _)| {
+            Arc::ptr_eq(other, device) && *number == interface.number
+        }) else {
+            return;
+        };
+        let (_, _, disks) = all_disks.swap_remove(pos);
+        drop(all_disks);
+
+        for disk in disks {
+            disk.mark_detached();
+            notify(DiskEvent::Detached(disk));
+        }
+    }
+}
+
+/// Returns the information of the LUN if it is a disk that is ready.
+fn probe_disk(transport: &BulkOnly, lun: u8) -> Option<DiskInfo> {
+    const INQUIRY_LEN: usize = 36;
+
+    let name = transport.device().name();
+
+    let mut inquiry_data = [0u8; INQUIRY_LEN];
+    let inquiry = Cdb::inquiry(INQUIRY_LEN as u8);
+    let len = transport
+        .execute(lun, &inquiry, DataStage::In(&mut inquiry_data))
+        .ok()?;
+    // The peripheral qualifier and the peripheral device type. Only the direct access block
+    // devices that are connected are supported.
+    if len < INQUIRY_LEN || inquiry_data[0] & 0x1F != 0 {
+        return None;
+    }
+
+    // The disk reports a unit attention condition after it is reset, and may take a while to
+    // spin up.
+    let mut nr_retries = 0;
+    loop {
+        match transport.execute(lun, &Cdb::test_unit_ready(), DataStage::None) {
+            Ok(_) => break,
+            Err(CommandError::Failed(Some(SENSE_KEY_UNIT_ATTENTION | SENSE_KEY_NOT_READY)))
+                if nr_retries < MAX_READY_RETRIES =>
+            {
+                nr_retries += 1;
+                delay(READY_RETRY_INTERVAL);
+            }
+            Err(err) => {
+                // The removable medium may be absent, e.g., in an empty card reader.
+                info!("USB disk {}:{} is not ready: {:?}", name, lun, err);
+                return None;
+            }
+        }
+    }
+
+    let (nr_blocks, block_size) = read_capacity(transport, lun)?;
+    if !block_size.is_power_of_two() || !(SECTOR_SIZE..=PAGE_SIZE).contains(&block_size) {
+        warn!(
+            "USB disk {}:{} has an unsupported block size {}",
+            name, lun, block_size
+        );
+        return None;
+    }
+
+    info!(
+        "USB disk {}:{}: {} {}",
+        name,
+        lun,
+        String::from_utf8_lossy(&inquiry_data[8..16]).trim_end(),
+        String::from_utf8_lossy(&inquiry_data[16..32]).trim_end()
+    );
+
+    Some(DiskInfo {
+        lun,
+        nr_blocks,
+        lba_shift: block_size.ilog2(),
+    })
+}
+
+/// Returns the number of the logical blocks and the size of a logical block.
+///
+/// READ CAPACITY (16) is used only if the disk is too large for READ CAPACITY (10).
+fn read_capacity(transport: &BulkOnly, lun: u8) -> Option<(u64, usize)> {
+    const READ_CAPACITY_10_LEN: usize = 8;
+    const READ_CAPACITY_16_LEN: usize = 32;
+
+    let name = transport.device().name();
+
+    let mut data = [0u8; READ_CAPACITY_16_LEN];
+    let result = transport.execute(
+        lun,
+        &Cdb::read_capacity_10(),
+        DataStage::In(&mut data[..READ_CAPACITY_10_LEN]),
+    );
+    if let Err(err) = result {
+        warn!(
+            "failed to read the capacity of USB disk {}:{}: {:?}",
+            name, lun, err
+        );
+        return None;
+    }
+    let last_lba = u32::from_be_bytes(data[0..4].try_into().unwrap());
+    let block_size = u32::from_be_bytes(data[4..8].try_into().unwrap()) as usize;
+    if last_lba != u32::MAX {
+        return Some((last_lba as u64 + 1, block_size));
+    }
+
+    let result = transport.execute(
+        lun,
+        &Cdb::read_capacity_16(READ_CAPACITY_16_LEN as u32),
+        DataStage::In(&mut data),
+    );
+    if let Err(err) = result {
+        warn!(
+            "failed to read the capacity of USB disk {}:{}: {:?}",
+            name, lun, err
+        );
+        return None;
+    }
+    let last_lba = u64::from_be_bytes(data[0..8].try_into().unwrap());
+    let block_size = u32::from_be_bytes(data[8..12].try_into().unwrap()) as usize;
+    Some((last_lba + 1, block_size))
+}
@@ -0,0 +1,242 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The Bulk-Only Transport (BOT), which wraps the SCSI commands in the bulk transfers.
+//!
+//! Reference: Universal Serial Bus Mass Storage Class Bulk-Only Transport, Revision 1.0.
+
+use alloc::sync::Arc;
+
+use log::warn;
+use ostd::sync::Mutex;
+
+use super::command::{Cdb, SENSE_SIZE, sense_key};
+use crate::{UsbDevice, UsbError, request::request_type};
+
+/// The signature of a command block wrapper (CBW), i.e., "USBC".
+const CBW_SIGNATURE: u32 = 0x4342_5355;
+const CBW_SIZE: usize = 31;
+/// The flag of a CBW whose data is transferred from the device to the host.
+const CBW_FLAG_DATA_IN: u8 = 0x80;
+
+/// The signature of a command status wrapper (CSW), i.e., "USBS".
+const CSW_SIGNATURE: u32 = 0x5342_5355;
+const CSW_SIZE: usize = 13;
+const CSW_STATUS_PASSED: u8 = 0x00;
+const CSW_STATUS_FAILED: u8 = 0x01;
+
+/// The class-specific requests.
+const REQUEST_RESET: u8 = 0xFF;
+const REQUEST_GET_MAX_LUN: u8 = 0xFE;
+
+/// An error that occurs when executing a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum CommandError {
+    /// The transfers fail.
+    Usb(UsbError),
+    /// The command fails, with the sense key if it is available.
+    Failed(Option<u8>),
+    /// The device and the host disagree on the state of the transport, which is reset.
+    PhaseError,
+}
+
+impl From<UsbError> for CommandError {
+    fn from(err: UsbError) -> Self {
+        Self::Usb(err)
+    }
+}
+
+/// The data stage of a command.
+pub(super) enum DataStage<'a> {
+    None,
+    In(&'a mut [u8]),
+    Out(&'a [u8]),
+}
+
+/// The Bulk-Only Transport of an interface.
+#[derive(Debug)]
+pub(super) struct BulkOnly {
+    device: Arc<UsbDevice>,
+    interface: u8,
+    bulk_in: u8,
+    bulk_out: u8,
+    /// The tag of the next CBW, which also serializes the commands.
+    next_tag: Mutex<u32>,
+}
+
+impl BulkOnly {
+    pub(super) fn new(device: Arc<UsbDevice>, interface: u8, bulk_in: u8, bulk_out: u8) -> Self {
+        Self {
+            device,
+            interface,
+            bulk_in,
+            bulk_out,
+            next_tag: Mutex::new(1),
+        }
+    }
+
+    pub(super) fn device(&self) -> &Arc<UsbDevice> {
+        &self.device
+    }
+
+    /// Returns the maximum LUN of the device.
+    ///
+    /// The devices that support only one LUN may stall the request.
+    pub(super) fn max_lun(&self) -> u8 {
+        let mut max_lun = [0u8; 1];
+        match self.device.control_in(
+            request_type::TYPE_CLASS | request_type::RECIPIENT_INTERFACE,
+            REQUEST_GET_MAX_LUN,
+            0,
+            self.interface as u16,
+            &mut max_lun,
+        ) {
+            Ok(1) => max_lun[0],
+            _ => 0,
+        }
+    }
+
+    /// Executes a command and requests the sense data if it fails.
+    ///
+    /// For an IN data stage, this method returns the number of the received bytes.
+    pub(super) fn execute(
+        &self,
+        lun: u8,
+        cdb: &Cdb,
+        data: DataStage<'_>,
+    ) -> Result<usize, CommandError> {
+        let mut next_tag = self.next_tag.lock();
+
+        match self.transport(&mut next_tag, lun, cdb, data) {
+            Err(CommandError::Failed(_)) => {
+                let mut sense = [0u8; SENSE_SIZE];
+                let request_sense = Cdb::request_sense(SENSE_SIZE as u8);
+                let sense_key = match self.transport(
+                    &mut next_tag,
+                    lun,
+                    &request_sense,
+                    DataStage::In(&mut sense),
+                ) {
+                    Ok(len) => sense_key(&sense[..len]),
+                    Err(_) => None,
+                };
+                Err(CommandError::Failed(sense_key))
+            }
+            result => result,
+        }
+    }
+
+    fn transport(
+        &self,
+        next_tag: &mut u32,
+        lun: u8,
+        cdb: &Cdb,
+        data: DataStage<'_>,
+    ) -> Result<usize, CommandError> {
+        let tag = *next_tag;
+        *next_tag = next_tag.wrapping_add(1);
+
+        // The command transport
+        let (len, flags) = match &data {
+            DataStage::None => (0, 0),
+            DataStage::In(buf) => (buf.len(), CBW_FLAG_DATA_IN),
+            DataStage::Out(buf) => (buf.len(), 0),
+        };
+        let mut cbw = [0u8; CBW_SIZE];
+        cbw[0..4].copy_from_slice(&CBW_SIGNATURE.to_le_bytes());
+        cbw[4..8].copy_from_slice(&tag.to_le_bytes());
+        cbw[8..12].copy_from_slice(&(len as u32).to_le_bytes());
+        cbw[12] = flags;
+        cbw[13] = lun;
+        cbw[14] = cdb.as_bytes().len() as u8;
+        cbw[15..15 + cdb.as_bytes().len()].copy_from_slice(cdb.as_bytes());
+        if let Err(err) = self.device.bulk_out(self.bulk_out, &cbw) {
+            self.reset_recovery();
+            return Err(err.into());
+        }
+
+        // The data transport, where the device stalls the endpoint if it has less data than
+        // expected or it cannot handle the command.
+        let transferred = match data {
+            DataStage::None => Ok(0),
+            DataStage::In(buf) => self.device.bulk_in(self.bulk_in, buf),
+            DataStage::Out(buf) => self.device.bulk_out(self.bulk_out, buf),
+        };
+        let transferred = match transferred {
+            Ok(transferred) => transferred,
+            Err(UsbError::Stall) => {
+                let endpoint = if flags == CBW_FLAG_DATA_IN {
+                    self.bulk_in
+                } else {
+                    self.bulk_out
+                };
+                self.device.clear_halt(endpoint)?;
+                0
+            }
+            Err(err) => {
+                self.reset_recovery();
+                return Err(err.into());
+            }
+        };
+
+        // The status transport, which is retried once if the endpoint stalls.
+        let mut csw = [0u8; CSW_SIZE];
+        let csw_len = match self.device.bulk_in(self.bulk_in, &mut csw) {
+            Err(UsbError::Stall) => {
+                self.device.clear_halt(self.bulk_in)?;
+                self.device.bulk_in(self.bulk_in, &mut csw)
+            }
+            result => result,
+        };
+        let csw_len = match csw_len {
+            Ok(csw_len) => csw_len,
+            Err(err) => {
+                self.reset_recovery();
+                return Err(err.into());
+            }
+        };
+
+        let signature = u32::from_le_bytes(csw[0..4].try_into().unwrap());
+        let csw_tag = u32::from_le_bytes(csw[4..8].try_into().unwrap());
+        if csw_len != CSW_SIZE || signature != CSW_SIGNATURE || csw_tag != tag {
+            warn!("USB device {}: invalid CSW", self.device.name());
+            self.reset_recovery();
+            return Err(CommandError::PhaseError);
+        }
+
+        match csw[12] {
+            CSW_STATUS_PASSED => Ok(transferred),
+            CSW_STATUS_FAILED => Err(CommandError::Failed(None)),
+            // The phase error and the reserved values
+            _ => {
+                self.reset_recovery();
+                Err(CommandError::PhaseError)
+            }
+        }
+    }
+
+    /// Resets the device and the bulk endpoints after a phase error.
+    fn reset_recovery(&self) {
+        if self.device.is_disconnected() {
+            return;
+        }
+
+        let result = self
+            .device
+            .control_out(
+                request_type::TYPE_CLASS | request_type::RECIPIENT_INTERFACE,
+                REQUEST_RESET,
+                0,
+                self.interface as u16,
+                &[],
+            )
+            .and_then(|_| self.device.clear_halt(self.bulk_in))
+            .and_then(|_| self.device.clear_halt(self.bulk_out));
+        if let Err(err) = result {
+            warn!(
+                "USB device {}: failed to reset the mass storage interface: {:?}",
+                self.device.name(),
+                err
+            );
+        }
+    }
+}
@@ -0,0 +1,207 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The synchronous transfers, which wait for the completions.
+
+use alloc::sync::Arc;
+use core::time::Duration;
+
+use ostd::{
+    mm::{HasDaddr, HasSize, PAGE_SIZE, VmIo, dma::DmaCoherent},
+    sync::{SpinLock, WaitQueue},
+};
+
+use crate::{
+    UsbError,
+    request::SetupPacket,
+    wait_for,
+    xhci::{CompletionHandler, Endpoint, Slot, split_at_boundary},
+};
+
+/// The timeout of the control transfers.
+const CONTROL_TIMEOUT: Duration = Duration::from_secs(5);
+/// The maximum size of the bounce buffer of a bulk transfer.
+const MAX_BULK_BUFFER_SIZE: usize = 64 << 10;
+
+/// A waiter of a transfer, which is completed in the interrupt context.
+struct Waiter {
+    result: SpinLock<Option<Result<usize, UsbError>>>,
+    wait_queue: WaitQueue,
+}
+
+impl Waiter {
+    fn new() -> Arc<Self> {
+        Arc::new(Self {
+            result: SpinLock::new(None),
+            wait_queue: WaitQueue::new(),
+        })
+    }
+
+    fn take(&self) -> Option<Result<usize, UsbError>> {
+        self.result.disable_irq().lock().take()
+    }
+}
+
+impl CompletionHandler for Waiter {
+    fn complete(&self, result: Result<usize, UsbError>) {
+        *self.result.disable_irq().lock() = Some(result);
+        self.wait_queue.wake_all();
+    }
+}
+
+/// Performs a control transfer whose data stage (if any) is IN, returning the number of the
+/// received bytes.
+pub(crate) fn control_in(
+    slot: &Slot,
+    mut setup: SetupPacket,
+    buf: &mut [u8],
+) -> Result<usize, UsbError> {
+    setup.length = buf
+        .len()
+        .try_into()
+        .map_err(|_| UsbError::InvalidArgument)?;
+
+    let buffer = alloc_buffer(buf.len());
+    let len = control(slot, &setup, buffer.as_ref())?;
+    if let Some(buffer) = buffer {
+        buffer.read_bytes(0, &mut buf[..len]).unwrap();
+    }
+
+    Ok(len)
+}
+
+/// Performs a control transfer whose data stage (if any) is OUT.
+pub(crate) fn control_out(
+    slot: &Slot,
+    mut setup: SetupPacket,
+    data: &[u8],
+) -> Result<(), UsbError> {
+    setup.length = data
+        .len()
+        .try_into()
+        .map_err(|_| UsbError::InvalidArgument)?;
+
+    let buffer = alloc_buffer(data.len());
+    if let Some(buffer) = buffer.as_ref() {
+        buffer.write_bytes(0, data).unwrap();
+    }
+    control(slot, &setup, buffer.as_ref())?;
+
+    Ok(())
+}
+
+fn control(
+    slot: &Slot,
+    setup: &SetupPacket,
+    buffer: Option<&DmaCoherent>,
+) -> Result<usize, UsbError> {
+    let ep0 = slot.ep0();
+    let waiter = Waiter::new();
+    let data = buffer.map(|buffer| (buffer.daddr(), setup.length as usize));
+    ep0.submit_control(setup, data, waiter.clone())?;
+
+    // The control transfers are polled for, since they may be performed during enumeration,
+    // where a broken device must not block the hub thread forever.
+    let mut result = None;
+    let is_completed = wait_for(
+        || {
+            slot.poll();
+            result = waiter.take();
+            result.is_some()
+        },
+        CONTROL_TIMEOUT,
+    );
+    if !is_completed {
+        slot.cancel(ep0)?;
+        return Err(UsbError::Timeout);
+    }
+
+    recover_on_error(slot, ep0, result.unwrap())
+}
+
+/// Performs a bulk or interrupt transfer whose direction is IN, returning the number of the
+/// received bytes.
+///
+/// The transfer ends early when the device sends a short packet.
+pub(crate) fn normal_in(
+    slot: &Slot,
+    endpoint: &Endpoint,
+    buf: &mut [u8],
+) -> Result<usize, UsbError> {
+    let Some(buffer) = alloc_buffer(buf.len().min(MAX_BULK_BUFFER_SIZE)) else {
+        return Ok(0);
+    };
+
+    let mut transferred = 0;
+    while transferred < buf.len() {
+        let len = chunk_len(&buffer, buf.len() - transferred);
+        let received = normal(slot, endpoint, &buffer, len)?;
+        buffer
+            .read_bytes(0, &mut buf[transferred..transferred + received])
+            .unwrap();
+        transferred += received;
+        if received < len {
+            break;
+        }
+    }
+
+    Ok(transferred)
+}
+
+/// Performs a bulk or interrupt transfer whose direction is OUT, returning the number of the
+/// sent bytes.
+pub(crate) fn normal_out(slot: &Slot, endpoint: &Endpoint, data: &[u8]) -> Result<usize, UsbError> {
+    let Some(buffer) = alloc_buffer(data.len().min(MAX_BULK_BUFFER_SIZE)) else {
+        return Ok(0);
+    };
+
+    let mut transferred = 0;
+    while transferred < data.len() {
+        let len = chunk_len(&buffer, data.len() - transferred);
+        buffer
+            .write_bytes(0, &data[transferred..transferred + len])
+            .unwrap();
+        transferred += normal(slot, endpoint, &buffer, len)?;
+    }
+
+    Ok(transferred)
+}
+
+fn normal(
+    slot: &Slot,
+    endpoint: &Endpoint,
+    buffer: &DmaCoherent,
+    len: usize,
+) -> Result<usize, UsbError> {
+    let waiter = Waiter::new();
+    endpoint.submit_normal(buffer.daddr(), len, waiter.clone())?;
+
+    // The device may not send data until it has some, so there is no timeout.
+    let result = waiter.wait_queue.wait_until(|| waiter.take());
+    recover_on_error(slot, endpoint, result)
+}
+
+/// Resets the endpoint if it is halted by an error.
+fn recover_on_error(
+    slot: &Slot,
+    endpoint: &Endpoint,
+    result: Result<usize, UsbError>,
+) -> Result<usize, UsbError> {
+    if let Err(UsbError::Stall | UsbError::Babble | UsbError::TransactionError) = result {
+        slot.cancel(endpoint)?;
+    }
+    result
+}
+
+/// Allocates a bounce buffer, which is `None` if the transfer has no data.
+fn alloc_buffer(len: usize) -> Option<DmaCoherent> {
+    if len == 0 {
+        return None;
+    }
+    Some(DmaCoherent::alloc(len.div_ceil(PAGE_SIZE), true).unwrap())
+}
+
+/// Returns the length of the next chunk, which must not cross a 64-KiB boundary.
+fn chunk_len(buffer: &DmaCoherent, remaining: usize) -> usize {
+    let len = remaining.min(buffer.size());
+    split_at_boundary(buffer.daddr(), len)[0].1
+}
@@ -0,0 +1,166 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The device contexts and the input contexts.
+//!
+//! Reference: eXtensible Host Controller Interface for Universal Serial Bus (xHCI), Revision
+//! 1.2, Section 6.2.
+
+use ostd::mm::{Daddr, HasDaddr, VmIo, dma::DmaCoherent};
+
+use crate::UsbSpeed;
+
+/// The endpoint types in the endpoint contexts.
+pub(super) mod endpoint_type {
+    pub(in crate::xhci) const ISOCH_OUT: u32 = 1;
+    pub(in crate::xhci) const BULK_OUT: u32 = 2;
+    pub(in crate::xhci) const INTERRUPT_OUT: u32 = 3;
+    pub(in crate::xhci) const CONTROL: u32 = 4;
+    pub(in crate::xhci) const ISOCH_IN: u32 = 5;
+    pub(in crate::xhci) const BULK_IN: u32 = 6;
+    pub(in crate::xhci) const INTERRUPT_IN: u32 = 7;
+}
+
+/// A slot context, which describes the device.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct SlotContext {
+    dwords: [u32; 8],
+}
+
+impl SlotContext {
+    /// Creates a slot context for a device at `route` behind the root hub port `root_port`.
+    pub(super) fn new(route: u32, speed: UsbSpeed, root_port: u8) -> Self {
+        let mut ctx = Self::default();
+        ctx.dwords[0] = (route & 0xF_FFFF) | ((speed.protocol_speed_id() as u32) << 20);
+        ctx.dwords[1] = (root_port as u32) << 16;
+        ctx
+    }
+
+    /// Sets the number of the last valid endpoint context.
+    pub(super) fn set_context_entries(&mut self, entries: u8) {
+        self.dwords[0] = (self.dwords[0] & !(0x1F << 27)) | ((entries as u32) << 27);
+    }
+
+    /// Sets the transaction translator (TT) that serves a low-speed or full-speed device behind
+    /// a high-speed hub.
+    pub(super) fn set_tt(&mut self, hub_slot_id: u8, port: u8) {
+        self.dwords[2] = (self.dwords[2] & !0xFFFF) | (hub_slot_id as u32) | ((port as u32) << 8);
+    }
+
+    /// Marks the device as a hub with `nr_ports` downstream ports.
+    ///
+    /// `tt_think_time` is the TT think time field in the hub descriptor.
+    pub(super) fn set_hub(&mut self, nr_ports: u8, tt_think_time: u8) {
+        const HUB: u32 = 1 << 26;
+
+        self.dwords[0] |= HUB;
+        self.dwords[1] = (self.dwords[1] & !(0xFF << 24)) | ((nr_ports as u32) << 24);
+        self.dwords[2] = (self.dwords[2] & !(0x3 << 16)) | (((tt_think_time & 0x3) as u32) << 16);
+    }
+}
+
+/// An endpoint context, which describes an endpoint of the device.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct EndpointContext {
+    dwords: [u32; 8],
+}
+
+impl EndpointContext {
+    /// Creates an endpoint context.
+    ///
+    /// `interval` is the exponent of the service interval in 125-microsecond units, and
+    /// `dequeue` and `cycle` describe the transfer ring.
+    pub(super) fn new(
+        type_: u32,
+        max_packet_size: u16,
+        max_burst: u8,
+        interval: u8,
+        dequeue: Daddr,
+        cycle: bool,
+    ) -> Self {
+        let is_isoch = matches!(type_, endpoint_type::ISOCH_IN | endpoint_type::ISOCH_OUT);
+        let is_periodic = is_isoch
+            || matches!(
+                type_,
+                endpoint_type::INTERRUPT_IN | endpoint_type::INTERRUPT_OUT
+            );
+        // The isochronous transfers are never retried.
+        let error_count = if is_isoch { 0 } else { 3 };
+        let max_esit_payload = if is_periodic {
+            max_packet_size as u32 * (max_burst as u32 + 1)
+        } else {
+            0
+        };
+        let average_trb_len = match type_ {
+            endpoint_type::CONTROL => 8,
+            _ if is_periodic => max_esit_payload.min(1024),
+            _ => 3072,
+        };
+
+        let mut ctx = Self::default();
+        ctx.dwords[0] = ((interval as u32) << 16) | ((max_esit_payload >> 16) << 24);
+        ctx.dwords[1] = (error_count << 1)
+            | (type_ << 3)
+            | ((max_burst as u32) << 8)
+            | ((max_packet_size as u32) << 16);
+        ctx.dwords[2] = (dequeue as u32) | cycle as u32;
+        ctx.dwords[3] = (dequeue as u64 >> 32) as u32;
+        ctx.dwords[4] = average_trb_len | ((max_esit_payload & 0xFFFF) << 16);
+        ctx
+    }
+
+    /// Returns the endpoint state, which is maintained by the controller.
+    pub(super) fn state(&self) -> u32 {
+        self.dwords[0] & 0x7
+    }
+
+    /// Sets the maximum packet size.
+    pub(super) fn set_max_packet_size(&mut self, max_packet_size: u16) {
+        self.dwords[1] = (self.dwords[1] & 0xFFFF) | ((max_packet_size as u32) << 16);
+    }
+}
+
+/// An input context, which is the input of the commands that change the device context.
+///
+/// The input control context comes first, followed by the slot context and the endpoint
+/// contexts. The size of each context is 32 or 64 bytes, depending on the controller.
+#[derive(Debug)]
+pub(super) struct InputContext {
+    memory: DmaCoherent,
+    context_size: usize,
+}
+
+impl InputContext {
+    pub(super) fn new(context_size: usize) -> Self {
+        Self {
+            memory: DmaCoherent::alloc(1, true).unwrap(),
+            context_size,
+        }
+    }
+
+    pub(super) fn daddr(&self) -> Daddr {
+        self.memory.daddr()
+    }
+
+    /// Clears the input control context, so that no contexts are dropped or added.
+    pub(super) fn clear(&self) {
+        self.memory.write_val(0, &[0u32; 2]).unwrap();
+    }
+
+    /// Marks the contexts as added (or to be evaluated), where bit `n` stands for the context of
+    /// device context index `n` and bit 0 stands for the slot context.
+    pub(super) fn add(&self, flags: u32) {
+        let old_flags: u32 = self.memory.read_val(4).unwrap();
+        self.memory.write_val(4, &(old_flags | flags)).unwrap();
+    }
+
+    pub(super) fn write_slot(&self, ctx: &SlotContext) {
+        self.memory.write_val(self.context_size, ctx).unwrap();
+    }
+
+    pub(super) fn write_endpoint(&self, dci: u8, ctx: &EndpointContext) {
+        let offset = self.context_size * (dci as usize + 1);
+        self.memory.write_val(offset, ctx).unwrap();
+    }
+}
@@ -0,0 +1,259 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{collections::vec_deque::VecDeque, sync::Arc, vec::Vec};
+
+use ostd::{
+    io::IoMem,
+    mm::{Daddr, VmIoOnce},
+    sync::SpinLock,
+};
+
+use super::{
+    ring::Ring,
+    trb::{Trb, completion_code},
+};
+use crate::{UsbError, request::SetupPacket};
+
+/// The boundary that the buffer of a TRB must not cross.
+const TRB_BUFFER_BOUNDARY: usize = 64 << 10;
+
+/// The handler of the completion of a transfer.
+///
+/// The handler is called in the interrupt context, or in the context that cancels the transfer.
+pub(crate) trait CompletionHandler: Send + Sync {
+    /// Completes the transfer with the number of the transferred bytes or an error.
+    fn complete(&self, result: Result<usize, UsbError>);
+}
+
+/// An endpoint of a device, which owns a transfer ring.
+///
+/// The transfers of an endpoint are completed in order.
+pub(crate) struct Endpoint {
+    slot_id: u8,
+    /// The device context index, which identifies the endpoint in the device.
+    dci: u8,
+    /// The doorbell register of the device slot.
+    doorbell: IoMem,
+    state: SpinLock<EndpointState>,
+}
+
+struct EndpointState {
+    ring: Ring,
+    pending: VecDeque<Td>,
+    /// Whether the slot is disabled, after which no transfers can be submitted.
+    is_disabled: bool,
+}
+
+/// A transfer descriptor (TD) that is outstanding.
+struct Td {
+    /// The data TRBs and their lengths, where the unused entries have zero lengths.
+    data_trbs: [(Daddr, usize); 2],
+    /// The last TRB, which raises the event that completes the TD.
+    last_trb: Daddr,
+    /// The number of the transferred bytes if a short packet is reported before the last TRB.
+    transferred: Option<usize>,
+    handler: Arc<dyn CompletionHandler>,
+}
+
+impl Td {
+    fn total_len(&self) -> usize {
+        self.data_trbs.iter().map(|(_, len)| len).sum()
+    }
+
+    /// Returns the number of the transferred bytes when the TRB at `trb_pointer` transfers less
+    /// than its length by `residual_len` bytes, or `None` if the TRB is not a data TRB.
+    fn transferred_at(&self, trb_pointer: Daddr, residual_len: usize) -> Option<usize> {
+        let index = self
+            .data_trbs
+            .iter()
+            .position(|(daddr, len)| *len != 0 && *daddr == trb_pointer)?;
+        let previous_len: usize = self.data_trbs[..index].iter().map(|(_, len)| len).sum();
+        Some(previous_len + self.data_trbs[index].1.saturating_sub(residual_len))
+    }
+
+    fn contains(&self, trb_pointer: Daddr) -> bool {
+        self.last_trb == trb_pointer
+            || self
+                .data_trbs
+                .iter()
+                .any(|(daddr, len)| *len != 0 && *daddr == trb_pointer)
+    }
+}
+
+impl Endpoint {
+    pub(super) fn new(slot_id: u8, dci: u8, doorbell: IoMem) -> Self {
+        Self {
+            slot_id,
+            dci,
+            doorbell,
+            state: SpinLock::new(EndpointState {
+                ring: Ring::new(),
+                pending: VecDeque::new(),
+                is_disabled: false,
+            }),
+        }
+    }
+
+    pub(super) fn slot_id(&self) -> u8 {
+        self.slot_id
+    }
+
+    pub(super) fn dci(&self) -> u8 {
+        self.dci
+    }
+
+    /// Returns the dequeue pointer and the cycle state that make the controller start from the
+    /// next TRB to be written.
+    pub(super) fn dequeue_state(&self) -> (Daddr, bool) {
+        let state = self.state.disable_irq().lock();
+        (state.ring.enqueue_daddr(), state.ring.cycle())
+    }
+
+    /// Submits a control transfer.
+    ///
+    /// `data` is the buffer of the data stage, which must be contiguous and no larger than 64
+    /// KiB.
+    pub(crate) fn submit_control(
+        &self,
+        setup: &SetupPacket,
+        data: Option<(Daddr, usize)>,
+        handler: Arc<dyn CompletionHandler>,
+    ) -> Result<(), UsbError> {
+        let is_in = setup.is_in();
+        let mut state = self.state.disable_irq().lock();
+        if state.is_disabled {
+            return Err(UsbError::Disconnected);
+        }
+
+        state.ring.push(Trb::setup_stage(setup));
+        let mut data_trbs = [(0, 0); 2];
+        if let Some((buffer, len)) = data.filter(|(_, len)| *len != 0) {
+            let [(first, first_len), (second, second_len)] = split_at_boundary(buffer, len);
+            let is_chained = second_len != 0;
+            let trb = Trb::data_stage(first, first_len, is_in, is_chained);
+            data_trbs[0] = (state.ring.push(trb), first_len);
+            if is_chained {
+                let trb = Trb::data_continuation(second, second_len);
+                data_trbs[1] = (state.ring.push(trb), second_len);
+            }
+        }
+        // The status stage is IN unless the data stage is IN.
+        let has_data_in = is_in && data_trbs[0].1 != 0;
+        let last_trb = state.ring.push(Trb::status_stage(!has_data_in));
+
+        state.pending.push_back(Td {
+            data_trbs,
+            last_trb,
+            transferred: None,
+            handler,
+        });
+        self.ring_doorbell();
+
+        Ok(())
+    }
+
+    /// Submits a bulk or interrupt transfer.
+    ///
+    /// The buffer must not cross a 64-KiB boundary.
+    pub(crate) fn submit_normal(
+        &self,
+        buffer: Daddr,
+        len: usize,
+        handler: Arc<dyn CompletionHandler>,
+    ) -> Result<(), UsbError> {
+        debug_assert_eq!(split_at_boundary(buffer, len)[1].1, 0);
+
+        let mut state = self.state.disable_irq().lock();
+        if state.is_disabled {
+            return Err(UsbError::Disconnected);
+        }
+
+        let trb = state.ring.push(Trb::normal(buffer, len));
+        state.pending.push_back(Td {
+            data_trbs: [(trb, len), (0, 0)],
+            last_trb: trb,
+            transferred: None,
+            handler,
+        });
+        self.ring_doorbell();
+
+        Ok(())
+    }
+
+    /// Handles a transfer event, returning the completed transfer if any.
+    pub(super) fn handle_event(
+        &self,
+        event: &Trb,
+    ) -> Option<(Arc<dyn CompletionHandler>, Result<usize, UsbError>)> {
+        let trb_pointer = event.trb_pointer();
+        let code = event.completion_code();
+
+        let mut state = self.state.disable_irq().lock();
+        // The stopped TDs are removed by the canceller.
+        if matches!(
+            code,
+            completion_code::STOPPED | completion_code::STOPPED_LENGTH_INVALID
+        ) {
+            return None;
+        }
+        let index = state
+            .pending
+            .iter()
+            .position(|td| td.contains(trb_pointer))?;
+
+        let td = &mut state.pending[index];
+        let result = match code {
+            completion_code::SUCCESS | completion_code::SHORT_PACKET => {
+                if let Some(transferred) = td.transferred_at(trb_pointer, event.residual_len()) {
+                    td.transferred = Some(transferred);
+                }
+                if trb_pointer != td.last_trb {
+                    // Wait for the event of the last TRB.
+                    return None;
+                }
+                Ok(td.transferred.unwrap_or_else(|| td.total_len()))
+            }
+            completion_code::STALL_ERROR => Err(UsbError::Stall),
+            completion_code::BABBLE_DETECTED => Err(UsbError::Babble),
+            completion_code::USB_TRANSACTION_ERROR => Err(UsbError::TransactionError),
+            code => Err(UsbError::HostError(code)),
+        };
+
+        let td = state.pending.remove(index).unwrap();
+        Some((td.handler, result))
+    }
+
+    /// Removes all the outstanding transfers, returning their handlers.
+    ///
+    /// The endpoint must be stopped or halted, or the slot must be disabled. Otherwise, the
+    /// controller may still access the buffers.
+    pub(super) fn take_pending(&self) -> Vec<Arc<dyn CompletionHandler>> {
+        let mut state = self.state.disable_irq().lock();
+        state.pending.drain(..).map(|td| td.handler).collect()
+    }
+
+    /// Marks the slot as disabled, and removes all the outstanding transfers.
+    pub(super) fn disable(&self) -> Vec<Arc<dyn CompletionHandler>> {
+        self.state.disable_irq().lock().is_disabled = true;
+        self.take_pending()
+    }
+
+    fn ring_doorbell(&self) {
+        self.doorbell.write_once(0, &(self.dci as u32)).unwrap();
+    }
+}
+
+impl core::fmt::Debug for Endpoint {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Endpoint")
+            .field("slot_id", &self.slot_id)
+            .field("dci", &self.dci)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Splits a buffer of at most 64 KiB so that no part crosses a 64-KiB boundary.
+pub(crate) fn split_at_boundary(buffer: Daddr, len: usize) -> [(Daddr, usize); 2] {
+    let first_len = len.min(TRB_BUFFER_BOUNDARY - buffer % TRB_BUFFER_BOUNDARY);
+    [(buffer, first_len), (buffer + first_len, len - first_len)]
+}
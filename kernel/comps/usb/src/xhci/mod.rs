@@ -0,0 +1,652 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The eXtensible Host Controller Interface (xHCI) driver.
+//!
+//! Reference: eXtensible Host Controller Interface for Universal Serial Bus (xHCI), Revision
+//! 1.2.
+
+mod context;
+mod endpoint;
+mod ring;
+mod slot;
+mod trb;
+
+use alloc::{
+    collections::btree_map::BTreeMap,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::time::Duration;
+
+use aster_pci::{
+    capability::{CapabilityData, msi::CapabilityMsiData, msix::CapabilityMsixData},
+    cfg_space::{Bar, Command},
+    common_device::PciCommonDevice,
+};
+use log::{info, warn};
+use ostd::{
+    arch::trap::TrapFrame,
+    io::IoMem,
+    irq::IrqLine,
+    mm::{Daddr, HasDaddr, VmIo, VmIoOnce, dma::DmaCoherent},
+    sync::{Mutex, SpinLock},
+};
+
+pub(crate) use self::{
+    endpoint::{CompletionHandler, Endpoint, split_at_boundary},
+    slot::{DevicePath, HubInfo, Slot},
+};
+use self::{
+    ring::{EventRing, Ring},
+    trb::{Trb, completion_code, trb_type},
+};
+use crate::{UsbError, UsbSpeed, hub, wait_for};
+
+/// The offsets of the capability registers.
+mod cap_reg {
+    /// Capability Register Length and Interface Version Number.
+    pub(super) const CAPLENGTH: usize = 0x00;
+    /// Structural Parameters 1.
+    pub(super) const HCSPARAMS1: usize = 0x04;
+    /// Structural Parameters 2.
+    pub(super) const HCSPARAMS2: usize = 0x08;
+    /// Capability Parameters 1.
+    pub(super) const HCCPARAMS1: usize = 0x10;
+    /// Doorbell Offset.
+    pub(super) const DBOFF: usize = 0x14;
+    /// Runtime Register Space Offset.
+    pub(super) const RTSOFF: usize = 0x18;
+}
+
+/// The offsets of the operational registers.
+mod op_reg {
+    /// USB Command.
+    pub(super) const USBCMD: usize = 0x00;
+    /// USB Status.
+    pub(super) const USBSTS: usize = 0x04;
+    /// Page Size.
+    pub(super) const PAGESIZE: usize = 0x08;
+    /// Command Ring Control.
+    pub(super) const CRCR: usize = 0x18;
+    /// Device Context Base Address Array Pointer.
+    pub(super) const DCBAAP: usize = 0x30;
+    /// Configure.
+    pub(super) const CONFIG: usize = 0x38;
+    /// Port Status and Control of the first port.
+    pub(super) const PORTSC: usize = 0x400;
+}
+
+/// The offsets of the registers of the primary interrupter.
+mod intr_reg {
+    /// Interrupter Management.
+    pub(super) const IMAN: usize = 0x00;
+    /// Interrupter Moderation.
+    pub(super) const IMOD: usize = 0x04;
+    /// Event Ring Segment Table Size.
+    pub(super) const ERSTSZ: usize = 0x08;
+    /// Event Ring Segment Table Base Address.
+    pub(super) const ERSTBA: usize = 0x10;
+    /// Event Ring Dequeue Pointer.
+    pub(super) const ERDP: usize = 0x18;
+}
+
+const USBCMD_RS: u32 = 1 << 0;
+const USBCMD_HCRST: u32 = 1 << 1;
+const USBCMD_INTE: u32 = 1 << 2;
+
+const USBSTS_HCH: u32 = 1 << 0;
+const USBSTS_EINT: u32 = 1 << 3;
+const USBSTS_CNR: u32 = 1 << 11;
+
+const IMAN_IP: u32 = 1 << 0;
+const IMAN_IE: u32 = 1 << 1;
+
+/// The Event Handler Busy bit of the Event Ring Dequeue Pointer register.
+const ERDP_EHB: u64 = 1 << 3;
+/// The Ring Cycle State bit of the Command Ring Control register.
+const CRCR_RCS: u64 = 1 << 0;
+
+/// The Current Connect Status bit of a PORTSC register.
+const PORTSC_CCS: u32 = 1 << 0;
+/// The Port Enabled bit, which disables the port if 1 is written.
+const PORTSC_PED: u32 = 1 << 1;
+/// The Port Reset bit.
+const PORTSC_PR: u32 = 1 << 4;
+/// The Port Power bit.
+const PORTSC_PP: u32 = 1 << 9;
+/// The Connect Status Change bit.
+const PORTSC_CSC: u32 = 1 << 17;
+/// The Warm Port Reset Change bit.
+const PORTSC_WRC: u32 = 1 << 19;
+/// The Port Reset Change bit.
+const PORTSC_PRC: u32 = 1 << 21;
+/// The Warm Port Reset bit, which is only for the USB 3 ports.
+const PORTSC_WPR: u32 = 1 << 31;
+/// All the change bits, which are cleared by writing 1.
+const PORTSC_CHANGES: u32 = 0x7F << 17;
+/// The bits that must be preserved when writing, i.e., the power, the indicators, and the
+/// wake-up enables.
+const PORTSC_PRESERVED: u32 = PORTSC_PP | (0x3 << 14) | (0x7 << 25);
+
+/// The size of the registers of each port.
+const PORT_REGS_SIZE: usize = 0x10;
+
+/// The interrupt moderation interval in 250-nanosecond units.
+const INTERRUPT_MODERATION: u32 = 160;
+
+/// The timeout of resetting or halting the controller.
+const CONTROLLER_TIMEOUT: Duration = Duration::from_secs(1);
+/// The timeout of the commands.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+/// The timeout of resetting a port.
+const PORT_RESET_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// An error that occurs when initializing an xHCI controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum XhciError {
+    /// The registers are not mapped by a memory BAR.
+    NoRegisters,
+    /// The controller supports neither MSI-X nor MSI.
+    NoInterrupt,
+    /// The controller cannot access the memory above 4 GiB, where the DMA buffers are allocated.
+    UnsupportedDma,
+    /// The controller does not support 4-KiB pages.
+    UnsupportedPageSize,
+    /// The controller does not respond in time.
+    Timeout,
+}
+
+/// The status of a root hub port or a downstream port of a hub.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PortStatus {
+    pub(crate) is_connected: bool,
+    /// Whether the device is connected again since the last status is reported.
+    pub(crate) has_connect_change: bool,
+}
+
+/// An xHCI controller, whose root hub ports form a USB bus.
+pub(crate) struct XhciController {
+    /// The index of the controller, which is also the USB bus number minus one.
+    index: u32,
+    operational: IoMem,
+    /// The registers of the primary interrupter.
+    interrupter: IoMem,
+    doorbells: IoMem,
+    nr_ports: u8,
+    /// The ranges of the root hub ports that support USB 3, as `(first port, count)`.
+    usb3_ports: Vec<(u8, u8)>,
+    /// The size of the contexts, which is 32 or 64 bytes.
+    context_size: usize,
+    /// The device context base address array (DCBAA).
+    dcbaa: DmaCoherent,
+    /// The scratchpad buffer array and the buffers, which are owned by the controller.
+    _scratchpads: Option<(DmaCoherent, Vec<DmaCoherent>)>,
+    command_ring: Mutex<Ring>,
+    /// The event that completes the last command.
+    command_completion: SpinLock<Option<Trb>>,
+    event_ring: SpinLock<EventRing>,
+    /// The endpoints of the enabled slots, indexed by the slot ID and the device context index.
+    endpoints: SpinLock<BTreeMap<(u8, u8), Arc<Endpoint>>>,
+    /// The interrupt capability, which keeps the interrupt handler registered.
+    _interrupt: Interrupt,
+}
+
+#[derive(Debug)]
+enum Interrupt {
+    Msix(CapabilityMsixData),
+    Msi(CapabilityMsiData),
+}
+
+impl XhciController {
+    /// Resets the controller and starts it with the root hub ports powered.
+    pub(crate) fn init(device: &PciCommonDevice, index: u32) -> Result<Arc<Self>, XhciError> {
+        let Some(Bar::Memory(bar)) = device.bar_manager().bar(0) else {
+            return Err(XhciError::NoRegisters);
+        };
+        let regs = bar.io_mem().clone();
+        device.write_command(device.read_command() | Command::MEMORY_SPACE | Command::BUS_MASTER);
+
+        let caplength: u32 = regs.read_once(cap_reg::CAPLENGTH).unwrap();
+        let hcsparams1: u32 = regs.read_once(cap_reg::HCSPARAMS1).unwrap();
+        let hcsparams2: u32 = regs.read_once(cap_reg::HCSPARAMS2).unwrap();
+        let hccparams1: u32 = regs.read_once(cap_reg::HCCPARAMS1).unwrap();
+        let dboff: u32 = regs.read_once(cap_reg::DBOFF).unwrap();
+        let rtsoff: u32 = regs.read_once(cap_reg::RTSOFF).unwrap();
+
+        let version = caplength >> 16;
+        let max_slots = (hcsparams1 & 0xFF) as u8;
+        let nr_ports = (hcsparams1 >> 24) as u8;
+        let nr_scratchpads = (((hcsparams2 >> 21) & 0x1F) << 5) | ((hcsparams2 >> 27) & 0x1F);
+        let supports_64bit_dma = hccparams1 & (1 << 0) != 0;
+        let context_size = if hccparams1 & (1 << 2) != 0 { 64 } else { 32 };
+        let ext_caps_offset = ((hccparams1 >> 16) << 2) as usize;
+        info!(
+            "xHCI controller {}: version {:#x}, slots {}, ports {}",
+            index, version, max_slots, nr_ports
+        );
+        if !supports_64bit_dma {
+            return Err(XhciError::UnsupportedDma);
+        }
+
+        let op_offset = (caplength & 0xFF) as usize;
+        let operational =
+            regs.slice(op_offset..op_offset + op_reg::PORTSC + PORT_REGS_SIZE * nr_ports as usize);
+        let intr_offset = (rtsoff & !0x1F) as usize + 0x20;
+        let interrupter = regs.slice(intr_offset..intr_offset + 0x20);
+        let db_offset = (dboff & !0x3) as usize;
+        let doorbells = regs.slice(db_offset..db_offset + 4 * (max_slots as usize + 1));
+
+        let mut usb3_ports = Vec::new();
+        if ext_caps_offset != 0 {
+            for_each_ext_cap(&regs, ext_caps_offset, |id, offset| match id {
+                ext_cap::LEGACY_SUPPORT => take_ownership(&regs, offset),
+                ext_cap::SUPPORTED_PROTOCOL => {
+                    let header: u32 = regs.read_once(offset).unwrap();
+                    let ports: u32 = regs.read_once(offset + 8).unwrap();
+                    if header >> 24 == 3 {
+                        usb3_ports.push(((ports & 0xFF) as u8, (ports >> 8) as u8));
+                    }
+                }
+                _ => {}
+            });
+        }
+
+        // Halt and reset the controller, which may be left running by the firmware.
+        let usbcmd: u32 = operational.read_once(op_reg::USBCMD).unwrap();
+        operational
+            .write_once(op_reg::USBCMD, &(usbcmd & !USBCMD_RS))
+            .unwrap();
+        let read_status = || -> u32 { operational.read_once(op_reg::USBSTS).unwrap() };
+        if !wait_for(|| read_status() & USBSTS_HCH != 0, CONTROLLER_TIMEOUT) {
+            return Err(XhciError::Timeout);
+        }
+        operational
+            .write_once(op_reg::USBCMD, &USBCMD_HCRST)
+            .unwrap();
+        if !wait_for(
+            || {
+                let usbcmd: u32 = operational.read_once(op_reg::USBCMD).unwrap();
+                usbcmd & USBCMD_HCRST == 0 && read_status() & USBSTS_CNR == 0
+            },
+            CONTROLLER_TIMEOUT,
+        ) {
+            return Err(XhciError::Timeout);
+        }
+
+        let page_size: u32 = operational.read_once(op_reg::PAGESIZE).unwrap();
+        if page_size & 1 == 0 {
+            return Err(XhciError::UnsupportedPageSize);
+        }
+
+        operational
+            .write_once(op_reg::CONFIG, &(max_slots as u32))
+            .unwrap();
+
+        let dcbaa = DmaCoherent::alloc(1, true).unwrap();
+        let scratchpads = (nr_scratchpads != 0).then(|| {
+            let array = DmaCoherent::alloc(1, true).unwrap();
+            let buffers: Vec<_> = (0..nr_scratchpads as usize)
+                .map(|i| {
+                    let buffer = DmaCoherent::alloc(1, true).unwrap();
+                    array.write_val(i * 8, &(buffer.daddr() as u64)).unwrap();
+                    buffer
+                })
+                .collect();
+            // Entry 0 of the DCBAA points to the scratchpad buffer array.
+            dcbaa.write_val(0, &(array.daddr() as u64)).unwrap();
+            (array, buffers)
+        });
+        write_u64(&operational, op_reg::DCBAAP, dcbaa.daddr() as u64);
+
+        let command_ring = Ring::new();
+        write_u64(
+            &operational,
+            op_reg::CRCR,
+            command_ring.enqueue_daddr() as u64 | CRCR_RCS,
+        );
+
+        let event_ring = EventRing::new();
+        interrupter.write_once(intr_reg::ERSTSZ, &1u32).unwrap();
+        write_u64(
+            &interrupter,
+            intr_reg::ERDP,
+            event_ring.dequeue_daddr() as u64,
+        );
+        write_u64(
+            &interrupter,
+            intr_reg::ERSTBA,
+            event_ring.segment_table_daddr() as u64,
+        );
+
+        // Prefer MSI-X, and fall back to MSI. Only the primary interrupter is used.
+        let msix = find_capability(device, |data| match data {
+            CapabilityData::Msix(msix) => Some(msix.clone()),
+            _ => None,
+        });
+        let msi = find_capability(device, |data| match data {
+            CapabilityData::Msi(msi) => Some(msi.clone()),
+            _ => None,
+        });
+        if msix.is_none() && msi.is_none() {
+            return Err(XhciError::NoInterrupt);
+        }
+
+        let controller = Arc::new_cyclic(|weak_self: &Weak<Self>| {
+            let weak_self = weak_self.clone();
+            let handle_irq = move |_: &TrapFrame| {
+                if let Some(controller) = weak_self.upgrade() {
+                    controller.handle_irq();
+                }
+            };
+            let interrupt = if let Some(mut msix) = msix {
+                msix.set_interrupt_vector(IrqLine::alloc().unwrap(), 0);
+                msix.irq_mut(0).unwrap().on_active(handle_irq);
+                Interrupt::Msix(msix)
+            } else {
+                let mut msi = msi.unwrap();
+                msi.set_interrupt_vector(IrqLine::alloc().unwrap());
+                msi.irq_mut().unwrap().on_active(handle_irq);
+                Interrupt::Msi(msi)
+            };
+
+            Self {
+                index,
+                operational,
+                interrupter,
+                doorbells,
+                nr_ports,
+                usb3_ports,
+                context_size,
+                dcbaa,
+                _scratchpads: scratchpads,
+                command_ring: Mutex::new(command_ring),
+                command_completion: SpinLock::new(None),
+                event_ring: SpinLock::new(event_ring),
+                endpoints: SpinLock::new(BTreeMap::new()),
+                _interrupt: interrupt,
+            }
+        });
+
+        controller
+            .interrupter
+            .write_once(intr_reg::IMOD, &INTERRUPT_MODERATION)
+            .unwrap();
+        controller
+            .interrupter
+            .write_once(intr_reg::IMAN, &(IMAN_IE | IMAN_IP))
+            .unwrap();
+        controller
+            .operational
+            .write_once(op_reg::USBCMD, &(USBCMD_RS | USBCMD_INTE))
+            .unwrap();
+
+        // Some controllers do not power the ports after reset.
+        for port in 1..=nr_ports {
+            let portsc = controller.read_portsc(port);
+            if portsc & PORTSC_PP == 0 {
+                controller.write_portsc(port, (portsc & PORTSC_PRESERVED) | PORTSC_PP);
+            }
+        }
+
+        Ok(controller)
+    }
+
+    pub(crate) fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub(crate) fn nr_ports(&self) -> u8 {
+        self.nr_ports
+    }
+
+    /// Returns the status of a root hub port, clearing the change bits.
+    pub(crate) fn port_status(&self, port: u8) -> PortStatus {
+        let portsc = self.read_portsc(port);
+        self.write_portsc(
+            port,
+            (portsc & PORTSC_PRESERVED) | (portsc & PORTSC_CHANGES),
+        );
+
+        PortStatus {
+            is_connected: portsc & PORTSC_CCS != 0,
+            has_connect_change: portsc & PORTSC_CSC != 0,
+        }
+    }
+
+    /// Resets a root hub port, returning the speed of the device if the port is enabled.
+    ///
+    /// The USB 3 ports are enabled after the link training, and they are only reset if the
+    /// link training fails.
+    pub(crate) fn reset_port(&self, port: u8) -> Option<UsbSpeed> {
+        let is_enabled = |portsc: u32| portsc & PORTSC_CCS != 0 && portsc & PORTSC_PED != 0;
+
+        let is_usb3 = self
+            .usb3_ports
+            .iter()
+            .any(|&(first, count)| (first..first.saturating_add(count)).contains(&port));
+        if !(is_usb3 && wait_for(|| is_enabled(self.read_portsc(port)), PORT_RESET_TIMEOUT)) {
+            let reset = if is_usb3 { PORTSC_WPR } else { PORTSC_PR };
+            let portsc = self.read_portsc(port);
+            self.write_portsc(port, (portsc & PORTSC_PRESERVED) | reset);
+
+            let changes = PORTSC_PRC | PORTSC_WRC;
+            if !wait_for(|| self.read_portsc(port) & changes != 0, PORT_RESET_TIMEOUT) {
+                warn!(
+                    "xHCI controller {}: port {} reset timeout",
+                    self.index, port
+                );
+            }
+            let portsc = self.read_portsc(port);
+            self.write_portsc(port, (portsc & PORTSC_PRESERVED) | (portsc & changes));
+        }
+
+        let portsc = self.read_portsc(port);
+        if !is_enabled(portsc) {
+            return None;
+        }
+        UsbSpeed::from_protocol_speed_id(((portsc >> 10) & 0xF) as u8)
+    }
+
+    /// Executes a command, returning the command completion event.
+    fn execute_command(&self, command: Trb) -> Result<Trb, UsbError> {
+        let mut command_ring = self.command_ring.lock();
+        *self.command_completion.disable_irq().lock() = None;
+
+        let command_daddr = command_ring.push(command);
+        self.doorbells.write_once(0, &0u32).unwrap();
+
+        let mut completion = None;
+        let is_completed = wait_for(
+            || {
+                self.process_events();
+                completion = self
+                    .command_completion
+                    .disable_irq()
+                    .lock()
+                    .take()
+                    .filter(|event| event.trb_pointer() == command_daddr);
+                completion.is_some()
+            },
+            COMMAND_TIMEOUT,
+        );
+        if !is_completed {
+            warn!(
+                "xHCI controller {}: command {:#x} timeout",
+                self.index,
+                command.type_()
+            );
+            return Err(UsbError::Timeout);
+        }
+
+        let completion = completion.unwrap();
+        match completion.completion_code() {
+            completion_code::SUCCESS => Ok(completion),
+            completion_code::NO_SLOTS_AVAILABLE => Err(UsbError::NoResources),
+            code => Err(UsbError::HostError(code)),
+        }
+    }
+
+    /// Processes the posted events.
+    ///
+    /// This method is called in the interrupt context, and it is also called to poll for the
+    /// completions.
+    pub(crate) fn process_events(&self) {
+        let mut event_ring = self.event_ring.disable_irq().lock();
+
+        let mut has_events = false;
+        while let Some(event) = event_ring.pop() {
+            has_events = true;
+            self.handle_event(&event);
+        }
+
+        if has_events {
+            write_u64(
+                &self.interrupter,
+                intr_reg::ERDP,
+                event_ring.dequeue_daddr() as u64 | ERDP_EHB,
+            );
+        }
+    }
+
+    fn handle_event(&self, event: &Trb) {
+        match event.type_() {
+            trb_type::TRANSFER_EVENT => {
+                let key = (event.slot_id(), event.endpoint_id());
+                let endpoint = self.endpoints.disable_irq().lock().get(&key).cloned();
+                let completion = endpoint.and_then(|endpoint| endpoint.handle_event(event));
+                if let Some((handler, result)) = completion {
+                    handler.complete(result);
+                }
+            }
+            trb_type::COMMAND_COMPLETION_EVENT => {
+                *self.command_completion.disable_irq().lock() = Some(*event);
+            }
+            trb_type::PORT_STATUS_CHANGE_EVENT => {
+                hub::notify_root_hub(self.index);
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_irq(&self) {
+        self.interrupter
+            .write_once(intr_reg::IMAN, &(IMAN_IE | IMAN_IP))
+            .unwrap();
+        self.operational
+            .write_once(op_reg::USBSTS, &USBSTS_EINT)
+            .unwrap();
+
+        self.process_events();
+    }
+
+    fn context_size(&self) -> usize {
+        self.context_size
+    }
+
+    /// Returns the doorbell register of a device slot.
+    fn doorbell(&self, slot_id: u8) -> IoMem {
+        let offset = slot_id as usize * 4;
+        self.doorbells.slice(offset..offset + 4)
+    }
+
+    /// Sets the output device context of a device slot, or clears it if `daddr` is zero.
+    fn set_device_context(&self, slot_id: u8, daddr: Daddr) {
+        self.dcbaa
+            .write_val(slot_id as usize * 8, &(daddr as u64))
+            .unwrap();
+    }
+
+    fn register_endpoint(&self, endpoint: &Arc<Endpoint>) {
+        let key = (endpoint.slot_id(), endpoint.dci());
+        self.endpoints
+            .disable_irq()
+            .lock()
+            .insert(key, endpoint.clone());
+    }
+
+    fn unregister_endpoint(&self, endpoint: &Endpoint) {
+        let key = (endpoint.slot_id(), endpoint.dci());
+        self.endpoints.disable_irq().lock().remove(&key);
+    }
+
+    fn read_portsc(&self, port: u8) -> u32 {
+        let offset = op_reg::PORTSC + PORT_REGS_SIZE * (port as usize - 1);
+        self.operational.read_once(offset).unwrap()
+    }
+
+    fn write_portsc(&self, port: u8, value: u32) {
+        let offset = op_reg::PORTSC + PORT_REGS_SIZE * (port as usize - 1);
+        self.operational.write_once(offset, &value).unwrap();
+    }
+}
+
+impl core::fmt::Debug for XhciController {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("XhciController")
+            .field("index", &self.index)
+            .field("nr_ports", &self.nr_ports)
+            .field("context_size", &self.context_size)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The IDs of the extended capabilities.
+mod ext_cap {
+    pub(super) const LEGACY_SUPPORT: u8 = 1;
+    pub(super) const SUPPORTED_PROTOCOL: u8 = 2;
+}
+
+/// Calls `f` with the ID and the offset of each extended capability.
+fn for_each_ext_cap(regs: &IoMem, mut offset: usize, mut f: impl FnMut(u8, usize)) {
+    loop {
+        let header: u32 = regs.read_once(offset).unwrap();
+        f(header as u8, offset);
+
+        let next = ((header >> 8) & 0xFF) as usize;
+        if next == 0 {
+            break;
+        }
+        offset += next << 2;
+    }
+}
+
+/// Takes the ownership of the controller from the firmware, which may use it to emulate the
+/// legacy keyboards.
+fn take_ownership(regs: &IoMem, offset: usize) {
+    const BIOS_OWNED: u32 = 1 << 16;
+    const OS_OWNED: u32 = 1 << 24;
+    /// The SMI enable bits, and the SMI status bits that are cleared by writing 1.
+    const SMI_ENABLES: u32 = 0xE01F;
+    const SMI_STATUS: u32 = 0x7 << 29;
+
+    let legsup: u32 = regs.read_once(offset).unwrap();
+    regs.write_once(offset, &(legsup | OS_OWNED)).unwrap();
+    if !wait_for(
+        || regs.read_once::<u32>(offset).unwrap() & BIOS_OWNED == 0,
+        CONTROLLER_TIMEOUT,
+    ) {
+        warn!("the firmware does not release the xHCI controller");
+    }
+
+    let legctlsts: u32 = regs.read_once(offset + 4).unwrap();
+    regs.write_once(offset + 4, &((legctlsts & !SMI_ENABLES) | SMI_STATUS))
+        .unwrap();
+}
+
+fn find_capability<T>(
+    device: &PciCommonDevice,
+    f: impl FnMut(&CapabilityData) -> Option<T>,
+) -> Option<T> {
+    device
+        .capabilities()
+        .iter()
+        .map(|cap| cap.capability_data())
+        .find_map(f)
+}
+
+/// Writes a 64-bit register with two 32-bit writes, which all the controllers support.
+fn write_u64(regs: &IoMem, offset: usize, value: u64) {
+    regs.write_once(offset, &(value as u32)).unwrap();
+    regs.write_once(offset + 4, &((value >> 32) as u32))
+        .unwrap();
+}
@@ -0,0 +1,150 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The command rings, transfer rings, and event rings.
+//!
+//! Reference: eXtensible Host Controller Interface for Universal Serial Bus (xHCI), Revision
+//! 1.2, Section 4.9.
+
+use ostd::mm::{Daddr, HasDaddr, PAGE_SIZE, VmIo, VmIoOnce, dma::DmaCoherent};
+
+use super::trb::{TRB_SIZE, Trb};
+
+/// The number of TRBs in a ring, whose only segment occupies one page.
+const RING_SIZE: usize = PAGE_SIZE / TRB_SIZE;
+
+/// A ring that is produced by the software, i.e., a command ring or a transfer ring.
+///
+/// The last TRB of the segment is a link TRB that points back to the start of the segment. The
+/// caller must make sure that no more than `RING_SIZE - 1` TRBs are outstanding at a time, so
+/// that the ring cannot overflow.
+#[derive(Debug)]
+pub(super) struct Ring {
+    segment: DmaCoherent,
+    /// The index of the next TRB to be written.
+    enqueue: usize,
+    /// The producer cycle state.
+    cycle: bool,
+}
+
+impl Ring {
+    pub(super) fn new() -> Self {
+        Self {
+            segment: DmaCoherent::alloc(1, true).unwrap(),
+            enqueue: 0,
+            cycle: true,
+        }
+    }
+
+    /// Returns the device address of the next TRB to be written.
+    pub(super) fn enqueue_daddr(&self) -> Daddr {
+        self.segment.daddr() + self.enqueue * TRB_SIZE
+    }
+
+    /// Returns the producer cycle state.
+    pub(super) fn cycle(&self) -> bool {
+        self.cycle
+    }
+
+    /// Writes a TRB to the ring, returning its device address.
+    ///
+    /// The Cycle bit is written last, so the consumer never sees a partially written TRB.
+    pub(super) fn push(&mut self, trb: Trb) -> Daddr {
+        let daddr = self.enqueue_daddr();
+        self.write_trb(self.enqueue, &trb);
+
+        self.enqueue += 1;
+        if self.enqueue == RING_SIZE - 1 {
+            self.write_trb(RING_SIZE - 1, &Trb::link(self.segment.daddr()));
+            self.enqueue = 0;
+            self.cycle = !self.cycle;
+        }
+
+        daddr
+    }
+
+    fn write_trb(&self, index: usize, trb: &Trb) {
+        let offset = index * TRB_SIZE;
+        self.segment
+            .write_val(offset, &trb.without_control())
+            .unwrap();
+        self.segment
+            .write_once(
+                offset + Trb::CONTROL_OFFSET,
+                &trb.control_with_cycle(self.cycle),
+            )
+            .unwrap();
+    }
+}
+
+/// An entry of the event ring segment table.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+struct SegmentTableEntry {
+    base: u64,
+    size: u32,
+    _reserved: u32,
+}
+
+/// A ring that is produced by the controller, i.e., an event ring.
+#[derive(Debug)]
+pub(super) struct EventRing {
+    segment: DmaCoherent,
+    /// The event ring segment table, which has only one entry.
+    segment_table: DmaCoherent,
+    /// The index of the next TRB to be read.
+    dequeue: usize,
+    /// The consumer cycle state.
+    cycle: bool,
+}
+
+impl EventRing {
+    pub(super) fn new() -> Self {
+        let segment = DmaCoherent::alloc(1, true).unwrap();
+        let segment_table = DmaCoherent::alloc(1, true).unwrap();
+        let entry = SegmentTableEntry {
+            base: segment.daddr() as u64,
+            size: RING_SIZE as u32,
+            ..Default::default()
+        };
+        segment_table.write_val(0, &entry).unwrap();
+
+        Self {
+            segment,
+            segment_table,
+            dequeue: 0,
+            cycle: true,
+        }
+    }
+
+    /// Returns the device address of the event ring segment table.
+    pub(super) fn segment_table_daddr(&self) -> Daddr {
+        self.segment_table.daddr()
+    }
+
+    /// Returns the device address of the next TRB to be read.
+    pub(super) fn dequeue_daddr(&self) -> Daddr {
+        self.segment.daddr() + self.dequeue * TRB_SIZE
+    }
+
+    /// Pops the next event TRB, if the controller has posted it.
+    pub(super) fn pop(&mut self) -> Option<Trb> {
+        let offset = self.dequeue * TRB_SIZE;
+        let control: u32 = self
+            .segment
+            .read_once(offset + Trb::CONTROL_OFFSET)
+            .unwrap();
+        if Trb::cycle(control) != self.cycle {
+            return None;
+        }
+        let trb: Trb = self.segment.read_val(offset).unwrap();
+
+        // Unlike the other rings, the event ring has no link TRBs.
+        self.dequeue += 1;
+        if self.dequeue == RING_SIZE {
+            self.dequeue = 0;
+            self.cycle = !self.cycle;
+        }
+
+        Some(trb)
+    }
+}
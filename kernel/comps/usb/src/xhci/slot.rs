@@ -0,0 +1,325 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{collections::btree_map::BTreeMap, sync::Arc, vec::Vec};
+
+use ostd::{
+    mm::{HasDaddr, VmIo, dma::DmaCoherent},
+    sync::{Mutex, SpinLock},
+};
+
+use super::{
+    XhciController,
+    context::{EndpointContext, InputContext, SlotContext, endpoint_type},
+    endpoint::Endpoint,
+    trb::Trb,
+};
+use crate::{
+    UsbError, UsbSpeed,
+    descriptor::{EndpointDescriptor, TransferType},
+};
+
+/// The device context index of the default control endpoint.
+const CONTROL_DCI: u8 = 1;
+/// The flag of the slot context in the input control context.
+const SLOT_FLAG: u32 = 1 << 0;
+
+/// The endpoint states in the endpoint contexts.
+mod endpoint_state {
+    pub(super) const RUNNING: u32 = 1;
+    pub(super) const HALTED: u32 = 2;
+}
+
+/// Where a device is attached in the topology.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DevicePath {
+    /// The root hub port that the device is attached to, directly or through the hubs.
+    pub(crate) root_port: u8,
+    /// The route string, whose each 4-bit nibble is a downstream port of a hub.
+    pub(crate) route: u32,
+    /// The slot ID and the port of the high-speed hub whose transaction translator serves the
+    /// device, if the device is a low-speed or full-speed device behind a high-speed hub.
+    pub(crate) tt: Option<(u8, u8)>,
+}
+
+/// The information of a hub, which is needed by the controller to schedule the transactions.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct HubInfo {
+    pub(crate) nr_ports: u8,
+    /// The TT think time field in the hub characteristics.
+    pub(crate) tt_think_time: u8,
+}
+
+/// A device slot, which is the controller's view of a device.
+pub(crate) struct Slot {
+    controller: Arc<XhciController>,
+    slot_id: u8,
+    speed: UsbSpeed,
+    /// The output device context, which is written by the controller.
+    output_context: DmaCoherent,
+    input_context: Mutex<InputContext>,
+    ep0: Arc<Endpoint>,
+    /// The configured endpoints, indexed by the endpoint addresses.
+    endpoints: SpinLock<BTreeMap<u8, Arc<Endpoint>>>,
+}
+
+impl Slot {
+    /// Enables a slot for a device that has been reset, and assigns an address to the device.
+    pub(crate) fn enable(
+        controller: &Arc<XhciController>,
+        path: &DevicePath,
+        speed: UsbSpeed,
+    ) -> Result<Self, UsbError> {
+        let slot_id = controller.execute_command(Trb::enable_slot())?.slot_id();
+
+        let output_context = DmaCoherent::alloc(1, true).unwrap();
+        controller.set_device_context(slot_id, output_context.daddr());
+        let ep0 = Arc::new(Endpoint::new(
+            slot_id,
+            CONTROL_DCI,
+            controller.doorbell(slot_id),
+        ));
+        controller.register_endpoint(&ep0);
+
+        let slot = Self {
+            controller: controller.clone(),
+            slot_id,
+            speed,
+            output_context,
+            input_context: Mutex::new(InputContext::new(controller.context_size())),
+            ep0,
+            endpoints: SpinLock::new(BTreeMap::new()),
+        };
+        if let Err(err) = slot.address(path) {
+            slot.disable();
+            return Err(err);
+        }
+
+        Ok(slot)
+    }
+
+    fn address(&self, path: &DevicePath) -> Result<(), UsbError> {
+        let input = self.input_context.lock();
+        input.clear();
+        input.add(SLOT_FLAG | (1 << CONTROL_DCI));
+
+        let mut slot_ctx = SlotContext::new(path.route, self.speed, path.root_port);
+        slot_ctx.set_context_entries(CONTROL_DCI);
+        if let Some((hub_slot_id, port)) = path.tt {
+            slot_ctx.set_tt(hub_slot_id, port);
+        }
+        input.write_slot(&slot_ctx);
+
+        // The maximum packet size is fixed later if the device reports a different one.
+        let max_packet_size = match self.speed {
+            UsbSpeed::Low | UsbSpeed::Full => 8,
+            UsbSpeed::High => 64,
+            UsbSpeed::Super | UsbSpeed::SuperPlus => 512,
+        };
+        let (dequeue, cycle) = self.ep0.dequeue_state();
+        let ep_ctx = EndpointContext::new(
+            endpoint_type::CONTROL,
+            max_packet_size,
+            0,
+            0,
+            dequeue,
+            cycle,
+        );
+        input.write_endpoint(CONTROL_DCI, &ep_ctx);
+
+        self.controller
+            .execute_command(Trb::address_device(input.daddr(), self.slot_id))?;
+        Ok(())
+    }
+
+    pub(crate) fn slot_id(&self) -> u8 {
+        self.slot_id
+    }
+
+    /// Returns the default control endpoint.
+    pub(crate) fn ep0(&self) -> &Arc<Endpoint> {
+        &self.ep0
+    }
+
+    /// Returns the configured endpoint with the address.
+    pub(crate) fn endpoint(&self, address: u8) -> Option<Arc<Endpoint>> {
+        self.endpoints.lock().get(&address).cloned()
+    }
+
+    /// Polls for the completions of the transfers.
+    pub(crate) fn poll(&self) {
+        self.controller.process_events();
+    }
+
+    /// Sets the maximum packet size of the default control endpoint, which is reported by the
+    /// device descriptor.
+    pub(crate) fn set_ep0_max_packet_size(&self, max_packet_size: u16) -> Result<(), UsbError> {
+        let input = self.input_context.lock();
+        input.clear();
+        input.add(1 << CONTROL_DCI);
+
+        let mut ep_ctx = self.read_output_endpoint(CONTROL_DCI);
+        ep_ctx.set_max_packet_size(max_packet_size);
+        input.write_endpoint(CONTROL_DCI, &ep_ctx);
+
+        self.controller
+            .execute_command(Trb::evaluate_context(input.daddr(), self.slot_id))?;
+        Ok(())
+    }
+
+    /// Configures the endpoints of the selected configuration.
+    ///
+    /// The isochronous endpoints are not supported, so they are ignored.
+    pub(crate) fn configure(
+        &self,
+        endpoints: &[EndpointDescriptor],
+        hub: Option<HubInfo>,
+    ) -> Result<(), UsbError> {
+        let input = self.input_context.lock();
+        input.clear();
+
+        let mut flags = SLOT_FLAG;
+        let mut last_dci = CONTROL_DCI;
+        let mut new_endpoints = Vec::new();
+        for desc in endpoints {
+            let type_ = match (desc.transfer_type(), desc.is_in()) {
+                (TransferType::Bulk, false) => endpoint_type::BULK_OUT,
+                (TransferType::Bulk, true) => endpoint_type::BULK_IN,
+                (TransferType::Interrupt, false) => endpoint_type::INTERRUPT_OUT,
+                (TransferType::Interrupt, true) => endpoint_type::INTERRUPT_IN,
+                _ => continue,
+            };
+            let dci = desc.number() * 2 + desc.is_in() as u8;
+
+            let endpoint = Arc::new(Endpoint::new(
+                self.slot_id,
+                dci,
+                self.controller.doorbell(self.slot_id),
+            ));
+            let (dequeue, cycle) = endpoint.dequeue_state();
+            let ep_ctx = EndpointContext::new(
+                type_,
+                desc.max_packet_size,
+                desc.max_burst,
+                interval_exponent(self.speed, desc),
+                dequeue,
+                cycle,
+            );
+            input.write_endpoint(dci, &ep_ctx);
+
+            flags |= 1 << dci;
+            last_dci = last_dci.max(dci);
+            new_endpoints.push((desc.address, endpoint));
+        }
+
+        let mut slot_ctx: SlotContext = self.output_context.read_val(0).unwrap();
+        slot_ctx.set_context_entries(last_dci);
+        if let Some(hub) = hub {
+            slot_ctx.set_hub(hub.nr_ports, hub.tt_think_time);
+        }
+        input.write_slot(&slot_ctx);
+        input.add(flags);
+
+        // The endpoints are registered first, so no events can be missed.
+        for (_, endpoint) in new_endpoints.iter() {
+            self.controller.register_endpoint(endpoint);
+        }
+        let result = self
+            .controller
+            .execute_command(Trb::configure_endpoint(input.daddr(), self.slot_id));
+        if let Err(err) = result {
+            for (_, endpoint) in new_endpoints.iter() {
+                self.controller.unregister_endpoint(endpoint);
+            }
+            return Err(err);
+        }
+
+        self.endpoints.lock().extend(new_endpoints);
+        Ok(())
+    }
+
+    /// Cancels the outstanding transfers of an endpoint, which fail with
+    /// [`UsbError::Cancelled`].
+    ///
+    /// A halted endpoint is also reset, so that it can be used again.
+    pub(crate) fn cancel(&self, endpoint: &Endpoint) -> Result<(), UsbError> {
+        let dci = endpoint.dci();
+        let state = self.read_output_endpoint(dci).state();
+        if state == endpoint_state::RUNNING {
+            self.controller
+                .execute_command(Trb::stop_endpoint(self.slot_id, dci))?;
+        } else if state == endpoint_state::HALTED {
+            self.controller
+                .execute_command(Trb::reset_endpoint(self.slot_id, dci))?;
+        }
+
+        // Skip the cancelled TRBs.
+        let (dequeue, cycle) = endpoint.dequeue_state();
+        self.controller
+            .execute_command(Trb::set_tr_dequeue_pointer(
+                self.slot_id,
+                dci,
+                dequeue,
+                cycle,
+            ))?;
+
+        for handler in endpoint.take_pending() {
+            handler.complete(Err(UsbError::Cancelled));
+        }
+        Ok(())
+    }
+
+    /// Disables the slot after the device is disconnected.
+    ///
+    /// The outstanding transfers fail with [`UsbError::Disconnected`], and no more transfers
+    /// can be submitted.
+    pub(crate) fn disable(&self) {
+        let endpoints = core::iter::once(self.ep0.clone())
+            .chain(self.endpoints.lock().values().cloned())
+            .collect::<Vec<_>>();
+        for endpoint in endpoints.iter() {
+            for handler in endpoint.disable() {
+                handler.complete(Err(UsbError::Disconnected));
+            }
+        }
+
+        // The controller may still access the contexts until the slot is disabled.
+        let _ = self
+            .controller
+            .execute_command(Trb::disable_slot(self.slot_id));
+        for endpoint in endpoints.iter() {
+            self.controller.unregister_endpoint(endpoint);
+        }
+        self.controller.set_device_context(self.slot_id, 0);
+    }
+
+    fn read_output_endpoint(&self, dci: u8) -> EndpointContext {
+        let offset = self.controller.context_size() * dci as usize;
+        self.output_context.read_val(offset).unwrap()
+    }
+}
+
+impl core::fmt::Debug for Slot {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Slot")
+            .field("slot_id", &self.slot_id)
+            .field("speed", &self.speed)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Returns the exponent of the service interval of a periodic endpoint in 125-microsecond
+/// units.
+///
+/// The interval of a low-speed or full-speed interrupt endpoint is in frames (milliseconds),
+/// while the others are already in exponents.
+fn interval_exponent(speed: UsbSpeed, desc: &EndpointDescriptor) -> u8 {
+    match (desc.transfer_type(), speed) {
+        (TransferType::Interrupt, UsbSpeed::Low | UsbSpeed::Full) => {
+            let microframes = desc.interval.max(1) as u32 * 8;
+            (microframes.ilog2() as u8).clamp(3, 10)
+        }
+        (TransferType::Isochronous, UsbSpeed::Full) => desc.interval.clamp(1, 16) + 2,
+        (TransferType::Interrupt | TransferType::Isochronous, _) => desc.interval.clamp(1, 16) - 1,
+        _ => 0,
+    }
+}
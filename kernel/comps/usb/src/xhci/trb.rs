@@ -0,0 +1,246 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The transfer request blocks (TRBs), which are the entries of all the rings.
+//!
+//! Reference: eXtensible Host Controller Interface for Universal Serial Bus (xHCI), Revision
+//! 1.2, Section 6.4.
+
+use ostd::mm::Daddr;
+
+use crate::request::SetupPacket;
+
+/// The size of a TRB.
+pub(super) const TRB_SIZE: usize = size_of::<Trb>();
+
+/// The TRB types.
+pub(super) mod trb_type {
+    pub(in crate::xhci) const NORMAL: u8 = 1;
+    pub(in crate::xhci) const SETUP_STAGE: u8 = 2;
+    pub(in crate::xhci) const DATA_STAGE: u8 = 3;
+    pub(in crate::xhci) const STATUS_STAGE: u8 = 4;
+    pub(in crate::xhci) const LINK: u8 = 6;
+    pub(in crate::xhci) const ENABLE_SLOT: u8 = 9;
+    pub(in crate::xhci) const DISABLE_SLOT: u8 = 10;
+    pub(in crate::xhci) const ADDRESS_DEVICE: u8 = 11;
+    pub(in crate::xhci) const CONFIGURE_ENDPOINT: u8 = 12;
+    pub(in crate::xhci) const EVALUATE_CONTEXT: u8 = 13;
+    pub(in crate::xhci) const RESET_ENDPOINT: u8 = 14;
+    pub(in crate::xhci) const STOP_ENDPOINT: u8 = 15;
+    pub(in crate::xhci) const SET_TR_DEQUEUE_POINTER: u8 = 16;
+    pub(in crate::xhci) const TRANSFER_EVENT: u8 = 32;
+    pub(in crate::xhci) const COMMAND_COMPLETION_EVENT: u8 = 33;
+    pub(in crate::xhci) const PORT_STATUS_CHANGE_EVENT: u8 = 34;
+}
+
+/// The completion codes in the event TRBs.
+pub(super) mod completion_code {
+    pub(in crate::xhci) const SUCCESS: u8 = 1;
+    pub(in crate::xhci) const BABBLE_DETECTED: u8 = 3;
+    pub(in crate::xhci) const USB_TRANSACTION_ERROR: u8 = 4;
+    pub(in crate::xhci) const STALL_ERROR: u8 = 6;
+    pub(in crate::xhci) const NO_SLOTS_AVAILABLE: u8 = 9;
+    pub(in crate::xhci) const SHORT_PACKET: u8 = 13;
+    pub(in crate::xhci) const STOPPED: u8 = 26;
+    pub(in crate::xhci) const STOPPED_LENGTH_INVALID: u8 = 27;
+}
+
+/// The Cycle bit, which tells the consumer whether the TRB is valid.
+const CYCLE: u32 = 1 << 0;
+/// The Toggle Cycle bit of a link TRB.
+const TOGGLE_CYCLE: u32 = 1 << 1;
+/// The Interrupt-on Short Packet bit.
+const ISP: u32 = 1 << 2;
+/// The Chain bit, which links the TRB with the next one into a TD.
+const CHAIN: u32 = 1 << 4;
+/// The Interrupt On Completion bit.
+const IOC: u32 = 1 << 5;
+/// The Immediate Data bit, which means the parameter is the data instead of a pointer to it.
+const IDT: u32 = 1 << 6;
+/// The Direction bit of a data stage TRB or a status stage TRB, which is set for IN.
+const DIR_IN: u32 = 1 << 16;
+
+/// The Transfer Type field of a setup stage TRB.
+const TRT_OUT: u32 = 2 << 16;
+const TRT_IN: u32 = 3 << 16;
+
+/// A TRB.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct Trb {
+    parameter: u64,
+    status: u32,
+    control: u32,
+}
+
+impl Trb {
+    /// The offset of the control field, which contains the Cycle bit.
+    pub(super) const CONTROL_OFFSET: usize = 12;
+
+    fn new(type_: u8, parameter: u64, status: u32, flags: u32) -> Self {
+        Self {
+            parameter,
+            status,
+            control: ((type_ as u32) << 10) | flags,
+        }
+    }
+
+    /// Returns the control field with the Cycle bit set to `cycle`.
+    pub(super) fn control_with_cycle(&self, cycle: bool) -> u32 {
+        (self.control & !CYCLE) | cycle as u32
+    }
+
+    /// Returns the TRB without the control field, which must be written last.
+    pub(super) fn without_control(&self) -> Self {
+        Self {
+            control: 0,
+            ..*self
+        }
+    }
+
+    pub(super) fn cycle(control: u32) -> bool {
+        control & CYCLE != 0
+    }
+
+    /// Creates a link TRB that points to the start of the ring and toggles the cycle state.
+    pub(super) fn link(segment: Daddr) -> Self {
+        Self::new(trb_type::LINK, segment as u64, 0, TOGGLE_CYCLE)
+    }
+
+    /// Creates a normal TRB, which is the only TRB of a bulk or interrupt transfer.
+    pub(super) fn normal(buffer: Daddr, len: usize) -> Self {
+        Self::new(trb_type::NORMAL, buffer as u64, len as u32, ISP | IOC)
+    }
+
+    /// Creates a setup stage TRB with the setup packet as the immediate data.
+    pub(super) fn setup_stage(packet: &SetupPacket) -> Self {
+        let trt = match (packet.length, packet.is_in()) {
+            (0, _) => 0,
+            (_, true) => TRT_IN,
+            (_, false) => TRT_OUT,
+        };
+        let parameter = packet.request_type as u64
+            | (packet.request as u64) << 8
+            | (packet.value as u64) << 16
+            | (packet.index as u64) << 32
+            | (packet.length as u64) << 48;
+        Self::new(trb_type::SETUP_STAGE, parameter, 8, IDT | trt)
+    }
+
+    /// Creates a data stage TRB.
+    ///
+    /// If the buffer is split, the TRB is chained with a normal TRB created by
+    /// [`Self::data_continuation`].
+    pub(super) fn data_stage(buffer: Daddr, len: usize, is_in: bool, is_chained: bool) -> Self {
+        let dir = if is_in { DIR_IN } else { 0 };
+        let chain = if is_chained { CHAIN } else { 0 };
+        Self::new(
+            trb_type::DATA_STAGE,
+            buffer as u64,
+            len as u32,
+            ISP | dir | chain,
+        )
+    }
+
+    /// Creates a normal TRB that continues the data stage of a control transfer.
+    pub(super) fn data_continuation(buffer: Daddr, len: usize) -> Self {
+        Self::new(trb_type::NORMAL, buffer as u64, len as u32, ISP)
+    }
+
+    /// Creates a status stage TRB, whose direction is opposite to the data stage.
+    pub(super) fn status_stage(is_in: bool) -> Self {
+        let dir = if is_in { DIR_IN } else { 0 };
+        Self::new(trb_type::STATUS_STAGE, 0, 0, IOC | dir)
+    }
+
+    pub(super) fn enable_slot() -> Self {
+        Self::new(trb_type::ENABLE_SLOT, 0, 0, 0)
+    }
+
+    pub(super) fn disable_slot(slot_id: u8) -> Self {
+        Self::new(trb_type::DISABLE_SLOT, 0, 0, (slot_id as u32) << 24)
+    }
+
+    pub(super) fn address_device(input_context: Daddr, slot_id: u8) -> Self {
+        Self::new(
+            trb_type::ADDRESS_DEVICE,
+            input_context as u64,
+            0,
+            (slot_id as u32) << 24,
+        )
+    }
+
+    pub(super) fn configure_endpoint(input_context: Daddr, slot_id: u8) -> Self {
+        Self::new(
+            trb_type::CONFIGURE_ENDPOINT,
+            input_context as u64,
+            0,
+            (slot_id as u32) << 24,
+        )
+    }
+
+    pub(super) fn evaluate_context(input_context: Daddr, slot_id: u8) -> Self {
+        Self::new(
+            trb_type::EVALUATE_CONTEXT,
+            input_context as u64,
+            0,
+            (slot_id as u32) << 24,
+        )
+    }
+
+    pub(super) fn reset_endpoint(slot_id: u8, dci: u8) -> Self {
+        let flags = ((slot_id as u32) << 24) | ((dci as u32) << 16);
+        Self::new(trb_type::RESET_ENDPOINT, 0, 0, flags)
+    }
+
+    pub(super) fn stop_endpoint(slot_id: u8, dci: u8) -> Self {
+        let flags = ((slot_id as u32) << 24) | ((dci as u32) << 16);
+        Self::new(trb_type::STOP_ENDPOINT, 0, 0, flags)
+    }
+
+    /// Creates a Set TR Dequeue Pointer command, which moves the dequeue pointer of the transfer
+    /// ring to `dequeue` with the cycle state `cycle`.
+    pub(super) fn set_tr_dequeue_pointer(
+        slot_id: u8,
+        dci: u8,
+        dequeue: Daddr,
+        cycle: bool,
+    ) -> Self {
+        let flags = ((slot_id as u32) << 24) | ((dci as u32) << 16);
+        Self::new(
+            trb_type::SET_TR_DEQUEUE_POINTER,
+            dequeue as u64 | cycle as u64,
+            0,
+            flags,
+        )
+    }
+
+    pub(super) fn type_(&self) -> u8 {
+        ((self.control >> 10) & 0x3F) as u8
+    }
+
+    /// Returns the pointer to the TRB that an event TRB is about.
+    pub(super) fn trb_pointer(&self) -> Daddr {
+        self.parameter as Daddr
+    }
+
+    /// Returns the completion code of an event TRB.
+    pub(super) fn completion_code(&self) -> u8 {
+        (self.status >> 24) as u8
+    }
+
+    /// Returns the number of bytes that are not transferred, which is reported by a transfer
+    /// event TRB.
+    pub(super) fn residual_len(&self) -> usize {
+        (self.status & 0xFF_FFFF) as usize
+    }
+
+    /// Returns the slot ID of a command completion event TRB or a transfer event TRB.
+    pub(super) fn slot_id(&self) -> u8 {
+        (self.control >> 24) as u8
+    }
+
+    /// Returns the device context index of a transfer event TRB.
+    pub(super) fn endpoint_id(&self) -> u8 {
+        ((self.control >> 16) & 0x1F) as u8
+    }
+}
@@ -3,7 +3,6 @@
 use alloc::{
     boxed::Box,
     collections::BTreeMap,
-    format,
     string::String,
     sync::{Arc, Weak},
     vec::Vec,
@@ -14,9 +13,9 @@ use core::{
 };
 
 use aster_block::{
-    BlockDeviceMeta, EXTENDED_DEVICE_ID_ALLOCATOR, PartitionInfo, PartitionNode,
+    BlockDeviceMeta, EXTENDED_DEVICE_ID_ALLOCATOR, PartitionInfo, PartitionNode, disk_name,
     bio::{BioEnqueueError, BioStatus, BioType, SubmittedBio, bio_segment_pool_init},
-    request_queue::{BioRequest, BioRequestSingleQueue},
+    request_queue::{BioRequest, BioRequestQueue},
 };
 use aster_util::mem_obj_slice::Slice;
 use device_id::{DeviceId, MinorId};
@@ -51,7 +50,7 @@ static NR_BLOCK_DEVICE: AtomicU32 = AtomicU32::new(0);
 pub struct BlockDevice {
     device: Arc<DeviceInner>,
     /// The software staging queue.
-    queue: BioRequestSingleQueue,
+    queue: BioRequestQueue,
     id: DeviceId,
     name: String,
     partitions: SpinLock<Option<Vec<Arc<PartitionNode>>>>,
@@ -59,28 +58,6 @@ pub struct BlockDevice {
 }
 
 impl BlockDevice {
-    /// Returns the formatted device name.
-    ///
-    /// The device name starts at "vda". The 26th device is "vdz" and the 27th is "vdaa".
-    /// The last one for two lettered suffix is "vdzz" which is followed by "vdaaa".
-    fn formatted_device_name(mut index: u32) -> String {
-        const VIRTIO_DISK_PREFIX: &str = "vd";
-
-        let mut suffix = Vec::new();
-        loop {
-            suffix.push((b'a' + (index % 26) as u8) as char);
-            index /= 26;
-            if index == 0 {
-                break;
-            }
-            index -= 1;
-        }
-        suffix.reverse();
-        let mut name = String::from(VIRTIO_DISK_PREFIX);
-        name.extend(suffix);
-        name
-    }
-
     /// Creates a new VirtIO-Block driver and registers it.
     pub(crate) fn init(transport: Box<dyn VirtioTransport>) -> Result<(), VirtioDeviceError> {
         let device = DeviceInner::init(transport)?;
@@ -90,13 +67,13 @@ impl BlockDevice {
             VIRTIO_BLOCK_MAJOR_ID.get().unwrap().get(),
             MinorId::new(index * VIRTIO_DEVICE_MINORS),
         );
-        let name = Self::formatted_device_name(index);
+        let name = disk_name("vd", index);
 
         let block_device = Arc::new_cyclic(|weak_self| BlockDevice {
             device,
             // Each bio request includes an additional 1 request and 1 response descriptor,
             // therefore this upper bound is set to (QUEUE_SIZE - 2).
-            queue: BioRequestSingleQueue::with_max_nr_segments_per_bio(
+            queue: BioRequestQueue::with_max_nr_segments_per_bio(
                 (DeviceInner::QUEUE_SIZE - 2) as usize,
             ),
             id,
@@ -120,7 +97,7 @@ impl BlockDevice {
             BioType::Read => self.device.read(request),
             BioType::Write => self.device.write(request),
             BioType::Flush => self.device.flush(request),
-            BioType::Discard => todo!(),
+            BioType::Discard => self.device.discard(request),
         }
     }
 
@@ -141,6 +118,8 @@ impl aster_block::BlockDevice for BlockDevice {
         BlockDeviceMeta {
             max_nr_segments_per_bio: self.queue.max_nr_segments_per_bio(),
             nr_sectors: self.device.config_manager.capacity_sectors(),
+            supports_fua: false,
+            max_discard_sectors: self.device.max_discard_sectors,
         }
     }
 
@@ -153,11 +132,14 @@ impl aster_block::BlockDevice for BlockDevice {
     }
 
     fn set_partitions(&self, infos: Vec<Option<PartitionInfo>>) {
-        let mut partitions = self.partitions.lock();
-        if let Some(old_partitions) = partitions.take() {
-            for partition in old_partitions {
-                let _ = aster_block::unregister(partition.id());
-            }
+        let old_partitions = self.partitions.lock().take();
+        for partition in old_partitions.into_iter().flatten() {
+            let _ = aster_block::unregister(partition.id());
+            // This does nothing if the ID is not an extended device ID.
+            EXTENDED_DEVICE_ID_ALLOCATOR
+                .get()
+                .unwrap()
+                .release(partition.id());
         }
 
         let mut new_partitions = Vec::new();
@@ -166,16 +148,16 @@ impl aster_block::BlockDevice for BlockDevice {
                 continue;
             };
 
-            let index = index as u32 + 1;
-            let id = if index < VIRTIO_DEVICE_MINORS {
-                DeviceId::new(self.id.major(), MinorId::new(self.id.minor().get() + index))
+            let number = index as u32 + 1;
+            let id = if number < VIRTIO_DEVICE_MINORS {
+                DeviceId::new(self.id.major(), MinorId::new(self.id.minor().get() + number))
             } else {
                 EXTENDED_DEVICE_ID_ALLOCATOR.get().unwrap().allocate()
             };
-            let name = format!("{}{}", self.name(), index);
+            let name = aster_block::partition_name(self.name(), number);
             let device = self.weak_self.upgrade().unwrap();
 
-            let partition = Arc::new(PartitionNode::new(id, name, device, *info));
+            let partition = Arc::new(PartitionNode::new(id, name, device, number, *info));
             new_partitions.push(partition);
         }
 
@@ -183,7 +165,7 @@ impl aster_block::BlockDevice for BlockDevice {
             let _ = aster_block::register(partition.clone());
         }
 
-        *partitions = Some(new_partitions);
+        *self.partitions.lock() = Some(new_partitions);
     }
 
     fn partitions(&self) -> Option<Vec<Arc<dyn aster_block::BlockDevice>>> {
@@ -195,6 +177,10 @@ impl aster_block::BlockDevice for BlockDevice {
             .collect();
         Some(devices)
     }
+
+    fn request_queue(&self) -> Option<&BioRequestQueue> {
+        Some(&self.queue)
+    }
 }
 
 #[derive(Debug)]
@@ -205,6 +191,11 @@ struct DeviceInner {
     transport: SpinLock<Box<dyn VirtioTransport>>,
     block_requests: Arc<DmaStream>,
     block_responses: Arc<DmaStream>,
+    /// The discard segments, one for each request.
+    discard_segments: Arc<DmaStream>,
+    /// The maximum number of sectors in a discard request, or zero if discarding is not
+    /// supported.
+    max_discard_sectors: usize,
     id_allocator: SyncIdAlloc,
     submitted_requests: SpinLock<BTreeMap<u16, SubmittedRequest>>,
 }
@@ -239,6 +230,13 @@ impl DeviceInner {
         assert!(Self::QUEUE_SIZE as usize * REQ_SIZE <= block_requests.size());
         let block_responses = Arc::new(DmaStream::alloc(1, false).unwrap());
         assert!(Self::QUEUE_SIZE as usize * RESP_SIZE <= block_responses.size());
+        let discard_segments = Arc::new(DmaStream::alloc(1, false).unwrap());
+        assert!(Self::QUEUE_SIZE as usize * DISCARD_SEGMENT_SIZE <= discard_segments.size());
+        let max_discard_sectors = if features.support_discard {
+            config_manager.max_discard_sectors()
+        } else {
+            0
+        };
 
         let device = Arc::new(Self {
             config_manager,
@@ -247,6 +245,8 @@ impl DeviceInner {
             transport: SpinLock::new(transport),
             block_requests,
             block_responses,
+            discard_segments,
+            max_discard_sectors,
             id_allocator: SyncIdAlloc::with_capacity(Self::QUEUE_SIZE as usize),
             submitted_requests: SpinLock::new(BTreeMap::new()),
         });
@@ -298,10 +298,10 @@ impl DeviceInner {
             resp_slice.sync_from_device().unwrap();
             let resp: BlockResp = resp_slice.read_val(0).unwrap();
             self.id_allocator.dealloc(id);
-            match RespStatus::try_from(resp.status).unwrap() {
-                RespStatus::Ok => {}
-                // FIXME: Return an error instead of triggering a kernel panic
-                _ => panic!("io error in block device"),
+            let status = match RespStatus::try_from(resp.status) {
+                Ok(RespStatus::Ok) => BioStatus::Complete,
+                Ok(RespStatus::Unsupported) => BioStatus::NotSupported,
+                _ => BioStatus::IoError,
             };
 
             // Synchronize DMA mapping if read from the device
@@ -319,7 +319,7 @@ impl DeviceInner {
 
             // Completes the bio request
             complete_request.bio_request.bios().for_each(|bio| {
-                bio.complete(BioStatus::Complete);
+                bio.complete(status);
             });
         }
     }
@@ -464,9 +464,10 @@ impl DeviceInner {
     }
 
     /// Flushes any cached data from the guest to the persistent storage on the host.
-    /// This will be ignored if the device doesn't support the `VIRTIO_BLK_F_FLUSH` feature.
+    /// This will be ignored if the device doesn't support the `VIRTIO_BLK_F_FLUSH` feature,
+    /// in which case the device has no volatile write cache.
     fn flush(&self, bio_request: BioRequest) {
-        if self.features.support_flush {
+        if !self.features.support_flush {
             bio_request.bios().for_each(|bio| {
                 bio.complete(BioStatus::Complete);
             });
@@ -516,6 +517,75 @@ impl DeviceInner {
             return;
         }
     }
+
+    /// Discards the sectors, this function is non-blocking.
+    fn discard(&self, bio_request: BioRequest) {
+        let num_sectors = bio_request.num_sectors();
+        if num_sectors > self.max_discard_sectors {
+            bio_request.bios().for_each(|bio| {
+                bio.complete(BioStatus::NotSupported);
+            });
+            return;
+        }
+
+        let id = self.id_allocator.alloc();
+        let req_slice = {
+            let req_slice = Slice::new(&self.block_requests, id * REQ_SIZE..(id + 1) * REQ_SIZE);
+            let req = BlockReq {
+                type_: ReqType::Discard as _,
+                reserved: 0,
+                sector: 0,
+            };
+            req_slice.write_val(0, &req).unwrap();
+            req_slice.sync_to_device().unwrap();
+            req_slice
+        };
+
+        let segment_slice = {
+            let segment_slice = Slice::new(
+                &self.discard_segments,
+                id * DISCARD_SEGMENT_SIZE..(id + 1) * DISCARD_SEGMENT_SIZE,
+            );
+            let segment = DiscardSegment {
+                sector: bio_request.sid_range().start.to_raw(),
+                num_sectors: num_sectors as u32,
+                flags: 0,
+            };
+            segment_slice.write_val(0, &segment).unwrap();
+            segment_slice.sync_to_device().unwrap();
+            segment_slice
+        };
+
+        let resp_slice = {
+            let resp_slice =
+                Slice::new(&self.block_responses, id * RESP_SIZE..(id + 1) * RESP_SIZE);
+            resp_slice.write_val(0, &BlockResp::default()).unwrap();
+            resp_slice.sync_to_device().unwrap();
+            resp_slice
+        };
+
+        let num_used_descs = 3;
+        loop {
+            let mut queue = self.queue.disable_irq().lock();
+            if num_used_descs > queue.available_desc() {
+                continue;
+            }
+            let token = queue
+                .add_dma_buf(&[&req_slice, &segment_slice], &[&resp_slice])
+                .expect("add queue failed");
+            if queue.should_notify() {
+                queue.notify();
+            }
+
+            // Records the submitted request
+            let submitted_request = SubmittedRequest::new(id as u16, bio_request);
+            self.submitted_requests
+                .disable_irq()
+                .lock()
+                .insert(token, submitted_request);
+            return;
+        }
+    }
 }
 
 /// A submitted bio request for callback.
@@ -551,6 +621,17 @@ struct BlockResp {
 
 const RESP_SIZE: usize = size_of::<BlockResp>();
 
+/// A segment of a VirtIOBlock discard request.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod)]
+struct DiscardSegment {
+    pub sector: u64,
+    pub num_sectors: u32,
+    pub flags: u32,
+}
+
+const DISCARD_SEGMENT_SIZE: usize = size_of::<DiscardSegment>();
+
 impl Default for BlockResp {
     fn default() -> Self {
         Self {
@@ -120,6 +120,7 @@ struct VirtioBlockTopology {
 #[repr(C)]
 struct VirtioBlockFeature {
     support_flush: bool,
+    support_discard: bool,
 }
 
 impl VirtioBlockConfig {
@@ -196,11 +197,22 @@ impl ConfigManager<VirtioBlockConfig> {
 
         (cap_high << 32) | cap_low
     }
+
+    /// Returns the maximum number of sectors in a discard segment.
+    ///
+    /// The value is only valid if the `VIRTIO_BLK_F_DISCARD` feature is negotiated.
+    pub(self) fn max_discard_sectors(&self) -> usize {
+        self.read_once::<u32>(offset_of!(VirtioBlockConfig, max_discard_sectors))
+            .unwrap() as usize
+    }
 }
 
 impl VirtioBlockFeature {
     pub(self) fn new(transport: &dyn VirtioTransport) -> Self {
-        let support_flush = transport.read_device_features() & BlockFeatures::FLUSH.bits() == 1;
-        VirtioBlockFeature { support_flush }
+        let features = BlockFeatures::from_bits_truncate(transport.read_device_features());
+        VirtioBlockFeature {
+            support_flush: features.contains(BlockFeatures::FLUSH),
+            support_discard: features.contains(BlockFeatures::DISCARD),
+        }
     }
 }
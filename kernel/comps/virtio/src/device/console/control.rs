@@ -0,0 +1,51 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use int_to_c_enum::TryFromInt;
+
+/// A control message that is exchanged over the control virtqueues.
+///
+/// For the `VIRTIO_CONSOLE_PORT_NAME` event, the name of the port follows the message.
+///
+/// Reference: The VirtIO spec 5.3.6.2 Multiport Device Operation.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+pub(super) struct ControlMessage {
+    /// The port ID
+    pub(super) id: u32,
+    /// The event, see [`ControlEvent`]
+    pub(super) event: u16,
+    /// The value of the event
+    pub(super) value: u16,
+}
+
+impl ControlMessage {
+    pub(super) fn new(id: u32, event: ControlEvent, value: u16) -> Self {
+        Self {
+            id,
+            event: event as u16,
+            value,
+        }
+    }
+}
+
+/// The events of the control messages.
+#[repr(u16)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+pub(super) enum ControlEvent {
+    /// The driver is ready to receive control messages (driver to device).
+    DeviceReady = 0,
+    /// A port is added (device to driver).
+    DeviceAdd = 1,
+    /// A port is removed (device to driver).
+    DeviceRemove = 2,
+    /// The port is ready for use (driver to device).
+    PortReady = 3,
+    /// The port is a console port (device to driver).
+    ConsolePort = 4,
+    /// The console size is changed (device to driver).
+    Resize = 5,
+    /// The port is opened or closed (both directions).
+    PortOpen = 6,
+    /// The name of the port is given (device to driver).
+    PortName = 7,
+}
@@ -1,70 +1,50 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use alloc::{boxed::Box, fmt::Debug, string::ToString, sync::Arc, vec::Vec};
-use core::hint::spin_loop;
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    sync::Arc,
+    vec,
+    vec::Vec,
+};
+use core::{
+    fmt::Debug,
+    hint::spin_loop,
+    sync::atomic::{AtomicU32, Ordering},
+};
 
-use aster_console::{AnyConsoleDevice, ConsoleCallback};
 use aster_util::mem_obj_slice::Slice;
-use log::debug;
+use log::{debug, warn};
 use ostd::{
     arch::trap::TrapFrame,
-    mm::{VmReader, dma::DmaStream, io::util::HasVmReaderWriter},
-    sync::{Rcu, SpinLock},
+    mm::{PAGE_SIZE, VmWriter, dma::DmaStream, io::util::HasVmReaderWriter},
+    sync::SpinLock,
 };
 
-use super::{DEVICE_NAME, config::VirtioConsoleConfig};
+use super::{
+    DEVICE_NAME,
+    config::VirtioConsoleConfig,
+    control::{ControlEvent, ControlMessage},
+    notify_port_handlers,
+    port::ConsolePort,
+    register_port,
+};
 use crate::{
     device::{VirtioDeviceError, console::config::ConsoleFeatures},
     queue::VirtQueue,
     transport::{ConfigManager, VirtioTransport},
 };
 
+/// The maximum number of ports supported for a virtio console device.
+///
+/// QEMU supports 31 ports for a device by default.
+const MAX_NR_PORTS: u32 = 32;
+
 pub struct ConsoleDevice {
     config_manager: ConfigManager<VirtioConsoleConfig>,
     transport: SpinLock<Box<dyn VirtioTransport>>,
-    receive_queue: SpinLock<VirtQueue>,
-    transmit_queue: SpinLock<VirtQueue>,
-    send_buffer: Arc<DmaStream>,
-    receive_buffer: Arc<DmaStream>,
-    #[expect(clippy::box_collection)]
-    callbacks: Rcu<Box<Vec<&'static ConsoleCallback>>>,
-}
-
-impl AnyConsoleDevice for ConsoleDevice {
-    fn send(&self, value: &[u8]) {
-        let mut transmit_queue = self.transmit_queue.disable_irq().lock();
-        let mut reader = VmReader::from(value);
-
-        while reader.remain() > 0 {
-            let mut writer = self.send_buffer.writer().unwrap();
-            let len = writer.write(&mut reader);
-            self.send_buffer.sync_to_device(0..len).unwrap();
-
-            let slice = Slice::new(&self.send_buffer, 0..len);
-            transmit_queue.add_dma_buf(&[&slice], &[]).unwrap();
-
-            if transmit_queue.should_notify() {
-                transmit_queue.notify();
-            }
-            while !transmit_queue.can_pop() {
-                spin_loop();
-            }
-            transmit_queue.pop_used().unwrap();
-        }
-    }
-
-    fn register_callback(&self, callback: &'static ConsoleCallback) {
-        loop {
-            let callbacks = self.callbacks.read();
-            let mut callbacks_cloned = callbacks.get().clone();
-            callbacks_cloned.push(callback);
-            if callbacks.compare_exchange(callbacks_cloned).is_ok() {
-                break;
-            }
-            // Contention on pushing, retry.
-            core::hint::spin_loop();
-        }
-    }
+    ports: Vec<Arc<ConsolePort>>,
+    control_queues: Option<Arc<ControlQueues>>,
 }
 
 impl Debug for ConsoleDevice {
@@ -72,104 +52,305 @@ impl Debug for ConsoleDevice {
         f.debug_struct("ConsoleDevice")
             .field("config", &self.config_manager.read_config())
             .field("transport", &self.transport)
-            .field("receive_queue", &self.receive_queue)
-            .field("transmit_queue", &self.transmit_queue)
-            .finish()
+            .field("ports", &self.ports)
+            .finish_non_exhaustive()
     }
 }
 
 impl ConsoleDevice {
     pub(crate) fn negotiate_features(features: u64) -> u64 {
-        let mut features = ConsoleFeatures::from_bits_truncate(features);
-        // A virtio console device may have multiple ports, but we only use one port to communicate now.
-        features.remove(ConsoleFeatures::VIRTIO_CONSOLE_F_MULTIPORT);
-        features.bits()
+        ConsoleFeatures::from_bits_truncate(features).bits()
     }
 
     pub(crate) fn init(mut transport: Box<dyn VirtioTransport>) -> Result<(), VirtioDeviceError> {
+        static NR_DEVICES: AtomicU32 = AtomicU32::new(0);
+
         let config_manager = VirtioConsoleConfig::new_manager(transport.as_ref());
-        debug!("virtio_console_config = {:?}", config_manager.read_config());
+        let config = config_manager.read_config();
+        debug!("virtio_console_config = {:?}", config);
 
-        const RECV0_QUEUE_INDEX: u16 = 0;
-        const TRANSMIT0_QUEUE_INDEX: u16 = 1;
-        let receive_queue =
-            SpinLock::new(VirtQueue::new(RECV0_QUEUE_INDEX, 2, transport.as_mut()).unwrap());
-        let transmit_queue =
-            SpinLock::new(VirtQueue::new(TRANSMIT0_QUEUE_INDEX, 2, transport.as_mut()).unwrap());
+        let features = ConsoleFeatures::from_bits_truncate(transport.read_device_features());
+        let is_multiport = features.contains(ConsoleFeatures::VIRTIO_CONSOLE_F_MULTIPORT);
 
-        let send_buffer = Arc::new(DmaStream::alloc(1, false).unwrap());
+        let (nr_ports, control_queues) = if is_multiport {
+            let max_nr_ports = (transport.num_queues() as u32).saturating_sub(2) / 2;
+            let nr_ports = config.max_nr_ports.min(max_nr_ports).min(MAX_NR_PORTS);
+            let control_queues = ControlQueues::new(transport.as_mut())?;
+            (nr_ports, Some(Arc::new(control_queues)))
+        } else {
+            (1, None)
+        };
 
-        let receive_buffer = Arc::new(DmaStream::alloc(1, false).unwrap());
+        let device_index = NR_DEVICES.fetch_add(1, Ordering::Relaxed);
+        let mut ports = Vec::with_capacity(nr_ports as usize);
+        for id in 0..nr_ports {
+            let port =
+                ConsolePort::new(device_index, id, control_queues.clone(), transport.as_mut())?;
+            ports.push(Arc::new(port));
+        }
 
         let device = Arc::new(Self {
             config_manager,
             transport: SpinLock::new(transport),
-            receive_queue,
-            transmit_queue,
-            send_buffer,
-            receive_buffer,
-            callbacks: Rcu::new(Box::new(Vec::new())),
+            ports,
+            control_queues,
         });
 
-        device.activate_receive_buffer(&mut device.receive_queue.disable_irq().lock());
+        for port in device.ports.iter() {
+            port.activate();
+        }
+        if let Some(control_queues) = device.control_queues.as_ref() {
+            control_queues.activate();
+        }
 
         // Register irq callbacks
         let mut transport = device.transport.disable_irq().lock();
-        let handle_console_input = {
-            let device = device.clone();
-            move |_: &TrapFrame| device.handle_recv_irq()
-        };
-        transport
-            .register_queue_callback(RECV0_QUEUE_INDEX, Box::new(handle_console_input), false)
-            .unwrap();
+        for port in device.ports.iter() {
+            let handle_port_input = {
+                let port = port.clone();
+                move |_: &TrapFrame| port.handle_recv_irq()
+            };
+            let (receive_queue_index, _) = ConsolePort::queue_indexes(port.id());
+            transport
+                .register_queue_callback(receive_queue_index, Box::new(handle_port_input), false)
+                .unwrap();
+        }
+        if device.control_queues.is_some() {
+            let handle_control_input = {
+                let device = device.clone();
+                move |_: &TrapFrame| device.handle_control_irq()
+            };
+            transport
+                .register_queue_callback(
+                    ControlQueues::RECV_QUEUE_INDEX,
+                    Box::new(handle_control_input),
+                    false,
+                )
+                .unwrap();
+        }
         transport
             .register_cfg_callback(Box::new(config_space_change))
             .unwrap();
         transport.finish_init();
         drop(transport);
 
-        aster_console::register_device(DEVICE_NAME.to_string(), device);
+        for port in device.ports.iter() {
+            register_port(port.clone());
+        }
+
+        match device.control_queues.as_ref() {
+            // The ports will be added via the control messages.
+            Some(control_queues) => {
+                control_queues.send_message(0, ControlEvent::DeviceReady, 1);
+            }
+            // Without multiple ports, port 0 is always present as a console port.
+            None => {
+                let port = &device.ports[0];
+                port.add();
+                port.set_host_connected(true);
+                device.set_console(port);
+                notify_port_handlers(port);
+            }
+        }
 
         Ok(())
     }
 
-    fn handle_recv_irq(&self) {
-        let mut receive_queue = self.receive_queue.disable_irq().lock();
+    fn handle_control_irq(&self) {
+        let control_queues = self.control_queues.as_ref().unwrap();
+        for (message, payload) in control_queues.receive_messages() {
+            self.handle_control_message(message, payload);
+        }
+    }
 
-        let Ok((_, len)) = receive_queue.pop_used() else {
+    /// Handles a control message received from the device.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/char/virtio_console.c>.
+    fn handle_control_message(&self, message: ControlMessage, payload: Vec<u8>) {
+        let Ok(event) = ControlEvent::try_from(message.event) else {
+            warn!("Virtio-Console: unknown control event {}", message.event);
             return;
         };
-        self.receive_buffer
-            .sync_from_device(0..len as usize)
-            .unwrap();
+        let Some(port) = self.ports.get(message.id as usize) else {
+            warn!(
+                "Virtio-Console: invalid port {} for control event {:?}",
+                message.id, event
+            );
+            return;
+        };
+        let control_queues = self.control_queues.as_ref().unwrap();
 
-        let callbacks = self.callbacks.read();
-        for callback in callbacks.get().iter() {
-            let mut reader = self.receive_buffer.reader().unwrap();
-            reader.limit(len as usize);
-            callback(reader);
+        match event {
+            ControlEvent::DeviceAdd => {
+                if !port.is_present() {
+                    port.add();
+                }
+                control_queues.send_message(port.id(), ControlEvent::PortReady, 1);
+            }
+            ControlEvent::DeviceRemove => port.remove(),
+            ControlEvent::ConsolePort => self.set_console(port),
+            ControlEvent::Resize => {
+                // TODO: Propagate the console size to the TTY.
+                debug!("Virtio-Console: port {} is resized", port.id());
+                return;
+            }
+            ControlEvent::PortOpen => port.set_host_connected(message.value != 0),
+            ControlEvent::PortName => {
+                let len = payload
+                    .iter()
+                    .position(|ch| *ch == 0)
+                    .unwrap_or(payload.len());
+                port.set_name(String::from_utf8_lossy(&payload[..len]).to_string());
+            }
+            ControlEvent::DeviceReady | ControlEvent::PortReady => {
+                warn!(
+                    "Virtio-Console: unexpected control event {:?} from the device",
+                    event
+                );
+                return;
+            }
         }
-        drop(callbacks);
 
-        self.activate_receive_buffer(&mut receive_queue);
+        notify_port_handlers(port);
     }
 
-    fn activate_receive_buffer(&self, receive_queue: &mut VirtQueue) {
-        receive_queue
-            // We limit the buffer length to one to work around a QEMU bug that causes incorrect
-            // results when pasting more than 32 bytes into the virtio console. This has no
-            // performance penalty, since QEMU always gets one byte at a time, regardless of
-            // whether we have this limit or not.
-            //
-            // For the QEMU bug, see details at
-            // <https://lore.kernel.org/qemu-devel/20240707111940.232549-3-lrh2000@pku.edu.cn/T/#u>.
-            .add_dma_buf(&[], &[&Slice::new(&self.receive_buffer, 0..1)])
-            .unwrap();
+    /// Marks the port as a console port.
+    ///
+    /// The first console port is also registered as a console device named [`DEVICE_NAME`], so
+    /// that the kernel messages can be printed to it.
+    fn set_console(&self, port: &Arc<ConsolePort>) {
+        if port.is_console() {
+            return;
+        }
+        port.set_console();
+
+        let is_registered = aster_console::all_devices_lock().contains_key(DEVICE_NAME);
+        if !is_registered {
+            aster_console::register_device(DEVICE_NAME.to_string(), port.clone());
+        }
+    }
+}
+
+/// The control virtqueues, which are available if `VIRTIO_CONSOLE_F_MULTIPORT` is negotiated.
+pub(super) struct ControlQueues {
+    receive_queue: SpinLock<VirtQueue>,
+    transmit_queue: SpinLock<VirtQueue>,
+    receive_buffer: DmaStream,
+    send_buffer: DmaStream,
+}
+
+impl ControlQueues {
+    const RECV_QUEUE_INDEX: u16 = 2;
+    const TRANSMIT_QUEUE_INDEX: u16 = 3;
+
+    /// The number of the buffers that are available for the device to send control messages.
+    ///
+    /// The device drops the control messages if no buffers are available, so there should be
+    /// enough buffers for the burst of control messages (e.g., when the ports are added).
+    const NR_RECV_BUFFERS: u16 = 16;
+    /// The size of a buffer that receives a control message and its payload.
+    const RECV_BUFFER_SIZE: usize = PAGE_SIZE / Self::NR_RECV_BUFFERS as usize;
+
+    fn new(transport: &mut dyn VirtioTransport) -> Result<Self, VirtioDeviceError> {
+        let receive_queue =
+            VirtQueue::new(Self::RECV_QUEUE_INDEX, Self::NR_RECV_BUFFERS, transport)?;
+        let transmit_queue = VirtQueue::new(Self::TRANSMIT_QUEUE_INDEX, 2, transport)?;
+
+        let receive_buffer =
+            DmaStream::alloc(1, false).map_err(|_| VirtioDeviceError::ResourceAllocError)?;
+        let send_buffer =
+            DmaStream::alloc(1, false).map_err(|_| VirtioDeviceError::ResourceAllocError)?;
+
+        Ok(Self {
+            receive_queue: SpinLock::new(receive_queue),
+            transmit_queue: SpinLock::new(transmit_queue),
+            receive_buffer,
+            send_buffer,
+        })
+    }
+
+    fn activate(&self) {
+        let mut receive_queue = self.receive_queue.disable_irq().lock();
+        for index in 0..Self::NR_RECV_BUFFERS {
+            let token = receive_queue
+                .add_dma_buf(&[], &[&self.receive_slice(index)])
+                .unwrap();
+            assert_eq!(token, index);
+        }
 
         if receive_queue.should_notify() {
             receive_queue.notify();
         }
     }
+
+    fn receive_slice(&self, index: u16) -> Slice<&DmaStream> {
+        let offset = index as usize * Self::RECV_BUFFER_SIZE;
+        Slice::new(
+            &self.receive_buffer,
+            offset..offset + Self::RECV_BUFFER_SIZE,
+        )
+    }
+
+    /// Receives the pending control messages and their payloads.
+    fn receive_messages(&self) -> Vec<(ControlMessage, Vec<u8>)> {
+        let mut receive_queue = self.receive_queue.disable_irq().lock();
+        let mut messages = Vec::new();
+
+        while let Ok((token, len)) = receive_queue.pop_used() {
+            let offset = token as usize * Self::RECV_BUFFER_SIZE;
+            let len = (len as usize).min(Self::RECV_BUFFER_SIZE);
+            self.receive_buffer
+                .sync_from_device(offset..offset + len)
+                .unwrap();
+
+            let mut reader = self.receive_buffer.reader().unwrap();
+            reader.skip(offset).limit(len);
+            match reader.read_val::<ControlMessage>() {
+                Ok(message) => {
+                    let mut payload = vec![0u8; reader.remain()];
+                    reader.read(&mut VmWriter::from(payload.as_mut_slice()));
+                    messages.push((message, payload));
+                }
+                Err(_) => warn!("Virtio-Console: truncated control message"),
+            }
+
+            let new_token = receive_queue
+                .add_dma_buf(&[], &[&self.receive_slice(token)])
+                .unwrap();
+            // This only works because nothing happen between `pop_used` and `add` that affects
+            // the list of free descriptors in the queue, so `add` reuses the descriptor which
+            // was just freed by `pop_used`.
+            assert_eq!(new_token, token);
+        }
+
+        if receive_queue.should_notify() {
+            receive_queue.notify();
+        }
+
+        messages
+    }
+
+    /// Sends a control message to the device.
+    pub(super) fn send_message(&self, id: u32, event: ControlEvent, value: u16) {
+        let mut transmit_queue = self.transmit_queue.disable_irq().lock();
+
+        let message = ControlMessage::new(id, event, value);
+        let mut writer = self.send_buffer.writer().unwrap();
+        writer.write_val(&message).unwrap();
+        let len = size_of::<ControlMessage>();
+        self.send_buffer.sync_to_device(0..len).unwrap();
+
+        let slice = Slice::new(&self.send_buffer, 0..len);
+        transmit_queue.add_dma_buf(&[&slice], &[]).unwrap();
+
+        if transmit_queue.should_notify() {
+            transmit_queue.notify();
+        }
+        while !transmit_queue.can_pop() {
+            spin_loop();
+        }
+        transmit_queue.pop_used().unwrap();
+    }
 }
 
 fn config_space_change(_: &TrapFrame) {
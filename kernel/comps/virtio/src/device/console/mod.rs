@@ -1,6 +1,59 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use alloc::{sync::Arc, vec::Vec};
+
+use ostd::sync::{LocalIrqDisabled, SpinLock};
+
+pub use self::port::ConsolePort;
+
 mod config;
+mod control;
 pub mod device;
+mod port;
 
+/// The name of the first console port in the console device registry.
 pub const DEVICE_NAME: &str = "Virtio-Console";
+
+/// A handler that is called when a port is added or removed, or the properties of the port (e.g.,
+/// whether it is a console port, its name, and whether the host side is connected) change.
+///
+/// The handler may be called in the interrupt context.
+pub trait ConsolePortHandler = Fn(&Arc<ConsolePort>) + Send + Sync + 'static;
+
+/// Registers a port handler.
+///
+/// The handler will be called immediately for each present port.
+pub fn register_port_handler(handler: impl ConsolePortHandler) {
+    let handler: Arc<dyn ConsolePortHandler> = Arc::new(handler);
+    PORT_HANDLERS.lock().push(handler.clone());
+
+    for port in all_ports() {
+        handler(&port);
+    }
+}
+
+/// Returns all the present ports.
+pub fn all_ports() -> Vec<Arc<ConsolePort>> {
+    PORTS
+        .lock()
+        .iter()
+        .filter(|port| port.is_present())
+        .cloned()
+        .collect()
+}
+
+fn register_port(port: Arc<ConsolePort>) {
+    PORTS.lock().push(port);
+}
+
+fn notify_port_handlers(port: &Arc<ConsolePort>) {
+    let handlers = PORT_HANDLERS.lock().clone();
+    for handler in handlers.iter() {
+        handler(port);
+    }
+}
+
+static PORTS: SpinLock<Vec<Arc<ConsolePort>>, LocalIrqDisabled> = SpinLock::new(Vec::new());
+
+static PORT_HANDLERS: SpinLock<Vec<Arc<dyn ConsolePortHandler>>, LocalIrqDisabled> =
+    SpinLock::new(Vec::new());
@@ -0,0 +1,250 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{boxed::Box, string::String, sync::Arc, vec::Vec};
+use core::{fmt::Debug, hint::spin_loop};
+
+use aster_console::{AnyConsoleDevice, ConsoleCallback};
+use aster_util::mem_obj_slice::Slice;
+use ostd::{
+    mm::{VmReader, dma::DmaStream, io::util::HasVmReaderWriter},
+    sync::{LocalIrqDisabled, Rcu, SpinLock},
+};
+
+use super::{control::ControlEvent, device::ControlQueues};
+use crate::{device::VirtioDeviceError, queue::VirtQueue, transport::VirtioTransport};
+
+/// A port of a virtio console device.
+///
+/// A port is either a console port, which serves as a hypervisor console (e.g., `hvc0`), or a
+/// generic port, which serves as a channel between the host and the guest (e.g., for a guest
+/// agent). If `VIRTIO_CONSOLE_F_MULTIPORT` is not negotiated, the device has only one port, which
+/// is a console port.
+pub struct ConsolePort {
+    device_index: u32,
+    id: u32,
+    receive_queue: SpinLock<VirtQueue>,
+    transmit_queue: SpinLock<VirtQueue>,
+    send_buffer: Arc<DmaStream>,
+    receive_buffer: Arc<DmaStream>,
+    control_queues: Option<Arc<ControlQueues>>,
+    #[expect(clippy::box_collection)]
+    callbacks: Rcu<Box<Vec<&'static ConsoleCallback>>>,
+    state: SpinLock<PortState, LocalIrqDisabled>,
+}
+
+#[derive(Debug, Default)]
+struct PortState {
+    is_present: bool,
+    is_console: bool,
+    name: Option<String>,
+    is_host_connected: bool,
+    is_guest_connected: bool,
+}
+
+impl AnyConsoleDevice for ConsolePort {
+    fn send(&self, value: &[u8]) {
+        let mut transmit_queue = self.transmit_queue.disable_irq().lock();
+        let mut reader = VmReader::from(value);
+
+        while reader.remain() > 0 {
+            let mut writer = self.send_buffer.writer().unwrap();
+            let len = writer.write(&mut reader);
+            self.send_buffer.sync_to_device(0..len).unwrap();
+
+            let slice = Slice::new(&self.send_buffer, 0..len);
+            transmit_queue.add_dma_buf(&[&slice], &[]).unwrap();
+
+            if transmit_queue.should_notify() {
+                transmit_queue.notify();
+            }
+            while !transmit_queue.can_pop() {
+                spin_loop();
+            }
+            transmit_queue.pop_used().unwrap();
+        }
+    }
+
+    fn register_callback(&self, callback: &'static ConsoleCallback) {
+        loop {
+            let callbacks = self.callbacks.read();
+            let mut callbacks_cloned = callbacks.get().clone();
+            callbacks_cloned.push(callback);
+            if callbacks.compare_exchange(callbacks_cloned).is_ok() {
+                break;
+            }
+            // Contention on pushing, retry.
+            core::hint::spin_loop();
+        }
+    }
+}
+
+impl Debug for ConsolePort {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ConsolePort")
+            .field("device_index", &self.device_index)
+            .field("id", &self.id)
+            .field("state", &*self.state.lock())
+            .finish_non_exhaustive()
+    }
+}
+
+impl ConsolePort {
+    pub(super) fn new(
+        device_index: u32,
+        id: u32,
+        control_queues: Option<Arc<ControlQueues>>,
+        transport: &mut dyn VirtioTransport,
+    ) -> Result<Self, VirtioDeviceError> {
+        let (receive_queue_index, transmit_queue_index) = Self::queue_indexes(id);
+        let receive_queue = VirtQueue::new(receive_queue_index, 2, transport)?;
+        let transmit_queue = VirtQueue::new(transmit_queue_index, 2, transport)?;
+
+        let send_buffer =
+            DmaStream::alloc(1, false).map_err(|_| VirtioDeviceError::ResourceAllocError)?;
+        let receive_buffer =
+            DmaStream::alloc(1, false).map_err(|_| VirtioDeviceError::ResourceAllocError)?;
+
+        Ok(Self {
+            device_index,
+            id,
+            receive_queue: SpinLock::new(receive_queue),
+            transmit_queue: SpinLock::new(transmit_queue),
+            send_buffer: Arc::new(send_buffer),
+            receive_buffer: Arc::new(receive_buffer),
+            control_queues,
+            callbacks: Rcu::new(Box::new(Vec::new())),
+            state: SpinLock::new(PortState::default()),
+        })
+    }
+
+    /// Returns the indexes of the receive queue and the transmit queue of the port.
+    ///
+    /// Reference: The VirtIO spec 5.3.2 Virtqueues.
+    pub(super) fn queue_indexes(id: u32) -> (u16, u16) {
+        if id == 0 {
+            (0, 1)
+        } else {
+            let receive_queue_index = (id * 2 + 2) as u16;
+            (receive_queue_index, receive_queue_index + 1)
+        }
+    }
+
+    /// Returns the index of the virtio console device to which the port belongs.
+    pub fn device_index(&self) -> u32 {
+        self.device_index
+    }
+
+    /// Returns the ID of the port in the virtio console device.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns whether the port has been added by the device and not removed yet.
+    pub fn is_present(&self) -> bool {
+        self.state.lock().is_present
+    }
+
+    /// Returns whether the port is a console port.
+    pub fn is_console(&self) -> bool {
+        self.state.lock().is_console
+    }
+
+    /// Returns the name of the port, if the device has given one.
+    pub fn name(&self) -> Option<String> {
+        self.state.lock().name.clone()
+    }
+
+    /// Returns whether the host side of the port is connected.
+    pub fn is_host_connected(&self) -> bool {
+        self.state.lock().is_host_connected
+    }
+
+    /// Sets whether the guest side of the port is connected, i.e., whether the port is opened by
+    /// the guest.
+    ///
+    /// The device will be notified of the change.
+    pub fn set_guest_connected(&self, is_connected: bool) {
+        let mut state = self.state.lock();
+        if state.is_guest_connected == is_connected {
+            return;
+        }
+        state.is_guest_connected = is_connected;
+        drop(state);
+
+        if let Some(control_queues) = self.control_queues.as_ref() {
+            control_queues.send_message(self.id, ControlEvent::PortOpen, is_connected as u16);
+        }
+    }
+
+    /// Marks the port as added by the device.
+    pub(super) fn add(&self) {
+        *self.state.lock() = PortState {
+            is_present: true,
+            ..PortState::default()
+        };
+    }
+
+    /// Marks the port as removed by the device.
+    pub(super) fn remove(&self) {
+        *self.state.lock() = PortState::default();
+    }
+
+    /// Marks the port as a console port.
+    ///
+    /// A console port is always regarded as opened by the guest.
+    pub(super) fn set_console(&self) {
+        self.state.lock().is_console = true;
+        self.set_guest_connected(true);
+    }
+
+    pub(super) fn set_name(&self, name: String) {
+        self.state.lock().name = Some(name);
+    }
+
+    pub(super) fn set_host_connected(&self, is_connected: bool) {
+        self.state.lock().is_host_connected = is_connected;
+    }
+
+    pub(super) fn handle_recv_irq(&self) {
+        let mut receive_queue = self.receive_queue.disable_irq().lock();
+
+        let Ok((_, len)) = receive_queue.pop_used() else {
+            return;
+        };
+        self.receive_buffer
+            .sync_from_device(0..len as usize)
+            .unwrap();
+
+        let callbacks = self.callbacks.read();
+        for callback in callbacks.get().iter() {
+            let mut reader = self.receive_buffer.reader().unwrap();
+            reader.limit(len as usize);
+            callback(reader);
+        }
+        drop(callbacks);
+
+        self.activate_receive_buffer(&mut receive_queue);
+    }
+
+    /// Makes the port ready to receive data from the device.
+    pub(super) fn activate(&self) {
+        self.activate_receive_buffer(&mut self.receive_queue.disable_irq().lock());
+    }
+
+    fn activate_receive_buffer(&self, receive_queue: &mut VirtQueue) {
+        receive_queue
+            // We limit the buffer length to one to work around a QEMU bug that causes incorrect
+            // results when pasting more than 32 bytes into the virtio console. This has no
+            // performance penalty, since QEMU always gets one byte at a time, regardless of
+            // whether we have this limit or not.
+            //
+            // For the QEMU bug, see details at
+            // <https://lore.kernel.org/qemu-devel/20240707111940.232549-3-lrh2000@pku.edu.cn/T/#u>.
+            .add_dma_buf(&[], &[&Slice::new(&self.receive_buffer, 0..1)])
+            .unwrap();
+
+        if receive_queue.should_notify() {
+            receive_queue.notify();
+        }
+    }
+}
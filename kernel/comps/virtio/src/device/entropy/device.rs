@@ -0,0 +1,126 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{boxed::Box, fmt::Debug, string::ToString, sync::Arc};
+
+use aster_hwrng::AnyHwRngDevice;
+use aster_util::mem_obj_slice::Slice;
+use log::debug;
+use ostd::{
+    arch::trap::TrapFrame,
+    mm::{PAGE_SIZE, VmWriter, dma::DmaStream, io::util::HasVmReaderWriter},
+    sync::{Mutex, SpinLock, WaitQueue},
+};
+
+use super::DEVICE_NAME;
+use crate::{device::VirtioDeviceError, queue::VirtQueue, transport::VirtioTransport};
+
+/// The quality of the random bytes from the device.
+///
+/// The device is trusted to provide almost full entropy, as in Linux.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/char/hw_random/virtio-rng.c>.
+const QUALITY: u16 = 1000;
+
+/// The maximum number of random bytes in a request.
+const REQUEST_BUFFER_SIZE: usize = PAGE_SIZE;
+
+pub struct EntropyDevice {
+    transport: SpinLock<Box<dyn VirtioTransport>>,
+    request_queue: SpinLock<VirtQueue>,
+    request_buffer: Arc<DmaStream>,
+    /// The lock that serializes the requests, because there is only one request buffer.
+    request_lock: Mutex<()>,
+    wait_queue: WaitQueue,
+}
+
+impl AnyHwRngDevice for EntropyDevice {
+    fn read(&self, buf: &mut [u8]) -> usize {
+        let len = buf.len().min(REQUEST_BUFFER_SIZE);
+        if len == 0 {
+            return 0;
+        }
+
+        let _guard = self.request_lock.lock();
+
+        let mut request_queue = self.request_queue.disable_irq().lock();
+        let slice = Slice::new(&self.request_buffer, 0..len);
+        request_queue.add_dma_buf(&[], &[&slice]).unwrap();
+        if request_queue.should_notify() {
+            request_queue.notify();
+        }
+        drop(request_queue);
+
+        let used_len = self.wait_queue.wait_until(|| {
+            let mut request_queue = self.request_queue.disable_irq().lock();
+            let (_, used_len) = request_queue.pop_used().ok()?;
+            Some((used_len as usize).min(len))
+        });
+        if used_len == 0 {
+            return 0;
+        }
+
+        self.request_buffer.sync_from_device(0..used_len).unwrap();
+        let mut reader = self.request_buffer.reader().unwrap();
+        reader.limit(used_len);
+        reader.read(&mut VmWriter::from(&mut buf[..used_len]))
+    }
+
+    fn quality(&self) -> u16 {
+        QUALITY
+    }
+}
+
+impl Debug for EntropyDevice {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("EntropyDevice")
+            .field("transport", &self.transport)
+            .field("request_queue", &self.request_queue)
+            .finish()
+    }
+}
+
+impl EntropyDevice {
+    pub(crate) fn negotiate_features(features: u64) -> u64 {
+        // The device has no device-specific features.
+        features
+    }
+
+    pub(crate) fn init(mut transport: Box<dyn VirtioTransport>) -> Result<(), VirtioDeviceError> {
+        const REQUEST_QUEUE_INDEX: u16 = 0;
+        let request_queue =
+            SpinLock::new(VirtQueue::new(REQUEST_QUEUE_INDEX, 1, transport.as_mut()).unwrap());
+
+        let request_buffer = Arc::new(DmaStream::alloc(1, false).unwrap());
+
+        let device = Arc::new(Self {
+            transport: SpinLock::new(transport),
+            request_queue,
+            request_buffer,
+            request_lock: Mutex::new(()),
+            wait_queue: WaitQueue::new(),
+        });
+
+        // Register irq callbacks
+        let mut transport = device.transport.disable_irq().lock();
+        let handle_request = {
+            let device = device.clone();
+            move |_: &TrapFrame| device.wait_queue.wake_all()
+        };
+        transport
+            .register_queue_callback(REQUEST_QUEUE_INDEX, Box::new(handle_request), false)
+            .unwrap();
+        transport
+            .register_cfg_callback(Box::new(config_space_change))
+            .unwrap();
+        transport.finish_init();
+        drop(transport);
+
+        aster_hwrng::register_device(DEVICE_NAME.to_string(), device);
+
+        Ok(())
+    }
+}
+
+fn config_space_change(_: &TrapFrame) {
+    debug!("Virtio-Rng device configuration space change");
+}
@@ -0,0 +1,5 @@
+// SPDX-License-Identifier: MPL-2.0
+
+pub mod device;
+
+pub const DEVICE_NAME: &str = "Virtio-Rng";
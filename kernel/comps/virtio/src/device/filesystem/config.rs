@@ -0,0 +1,70 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::string::String;
+use core::mem::offset_of;
+
+use aster_util::safe_ptr::SafePtr;
+use bitflags::bitflags;
+use ostd_pod::FromZeros;
+
+use crate::transport::{ConfigManager, VirtioTransport};
+
+/// The maximum length of a tag.
+pub(super) const TAG_LEN: usize = 36;
+
+bitflags! {
+    pub(super) struct FsFeatures: u64 {
+        /// The device supports the notification queue.
+        const VIRTIO_FS_F_NOTIFICATION = 1 << 0;
+    }
+}
+
+impl FsFeatures {
+    pub(super) const fn supported_features() -> Self {
+        Self::empty()
+    }
+}
+
+#[derive(Debug, Pod, Clone, Copy)]
+#[repr(C)]
+pub(super) struct VirtioFsConfig {
+    /// The name of the filesystem, which is encoded in UTF-8 and padded with NUL bytes
+    /// if it is shorter than the field.
+    pub tag: [u8; TAG_LEN],
+    pub num_request_queues: u32,
+    pub notify_buf_size: u32,
+}
+
+impl VirtioFsConfig {
+    pub(super) fn new_manager(transport: &dyn VirtioTransport) -> ConfigManager<Self> {
+        let safe_ptr = transport
+            .device_config_mem()
+            .map(|mem| SafePtr::new(mem, 0));
+        let bar_space = transport.device_config_bar();
+        ConfigManager::new(safe_ptr, bar_space)
+    }
+}
+
+impl ConfigManager<VirtioFsConfig> {
+    pub(super) fn read_config(&self) -> VirtioFsConfig {
+        let mut fs_config = VirtioFsConfig::new_zeroed();
+
+        for (i, byte) in fs_config.tag.iter_mut().enumerate() {
+            *byte = self
+                .read_once::<u8>(offset_of!(VirtioFsConfig, tag) + i)
+                .unwrap();
+        }
+        fs_config.num_request_queues = self
+            .read_once::<u32>(offset_of!(VirtioFsConfig, num_request_queues))
+            .unwrap();
+
+        fs_config
+    }
+
+    /// Reads the tag, with which the filesystem is mounted.
+    pub(super) fn read_tag(&self) -> String {
+        let tag = self.read_config().tag;
+        let len = tag.iter().position(|&byte| byte == 0).unwrap_or(TAG_LEN);
+        String::from_utf8_lossy(&tag[..len]).into_owned()
+    }
+}
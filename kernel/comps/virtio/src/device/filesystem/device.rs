@@ -0,0 +1,247 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{boxed::Box, collections::BTreeMap, sync::Arc, vec, vec::Vec};
+use core::{
+    fmt::Debug,
+    hint::spin_loop,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use aster_util::mem_obj_slice::Slice;
+use log::{debug, info, warn};
+use ostd::{
+    arch::trap::TrapFrame,
+    mm::{PAGE_SIZE, VmIo, dma::DmaStream},
+    sync::{SpinLock, WaitQueue},
+};
+
+use super::{
+    config::{FsFeatures, VirtioFsConfig},
+    register_device,
+};
+use crate::{
+    device::VirtioDeviceError,
+    queue::VirtQueue,
+    transport::{ConfigManager, VirtioTransport},
+};
+
+/// The queue of the requests without replies, e.g., `FORGET`.
+const QUEUE_HIPRIO: u16 = 0;
+/// The first request queue. The other request queues are not used.
+const QUEUE_REQUEST: u16 = 1;
+
+const QUEUE_SIZE: u16 = 64;
+
+/// A virtio filesystem device.
+///
+/// The requests of the FUSE protocol are sent to the device, which are served by the
+/// host (e.g., `virtiofsd`) and replied in the buffers given by the guest.
+pub struct FileSystemDevice {
+    config_manager: ConfigManager<VirtioFsConfig>,
+    hiprio_queue: SpinLock<VirtQueue>,
+    request_queue: SpinLock<VirtQueue>,
+    /// The buffers of the high-priority requests that are being processed by the device.
+    hiprio_buffers: SpinLock<BTreeMap<u16, Arc<DmaStream>>>,
+    /// The IDs of the requests that are being processed by the device, indexed by the
+    /// tokens of the request queue.
+    submitted_requests: SpinLock<BTreeMap<u16, u64>>,
+    /// The lengths of the replies, indexed by the IDs of the completed requests.
+    completed_requests: SpinLock<BTreeMap<u64, u32>>,
+    /// The wait queue of the threads waiting for the replies.
+    wait_queue: WaitQueue,
+    next_request_id: AtomicU64,
+    transport: SpinLock<Box<dyn VirtioTransport>>,
+}
+
+impl FileSystemDevice {
+    /// Negotiates features for the device specified bits 0~23.
+    pub(crate) fn negotiate_features(features: u64) -> u64 {
+        let device_features = FsFeatures::from_bits_truncate(features);
+        (device_features & FsFeatures::supported_features()).bits()
+    }
+
+    /// Creates a new virtio filesystem driver and registers it with its tag.
+    pub(crate) fn init(mut transport: Box<dyn VirtioTransport>) -> Result<(), VirtioDeviceError> {
+        let config_manager = VirtioFsConfig::new_manager(transport.as_ref());
+        debug!("virtio_fs_config = {:?}", config_manager.read_config());
+
+        let num_queues = transport.num_queues();
+        if num_queues < 2 {
+            return Err(VirtioDeviceError::QueuesAmountDoNotMatch(num_queues, 2));
+        }
+
+        let hiprio_queue = SpinLock::new(VirtQueue::new(
+            QUEUE_HIPRIO,
+            QUEUE_SIZE,
+            transport.as_mut(),
+        )?);
+        let request_queue = SpinLock::new(VirtQueue::new(
+            QUEUE_REQUEST,
+            QUEUE_SIZE,
+            transport.as_mut(),
+        )?);
+
+        let tag = config_manager.read_tag();
+        let device = Arc::new(Self {
+            config_manager,
+            hiprio_queue,
+            request_queue,
+            hiprio_buffers: SpinLock::new(BTreeMap::new()),
+            submitted_requests: SpinLock::new(BTreeMap::new()),
+            completed_requests: SpinLock::new(BTreeMap::new()),
+            wait_queue: WaitQueue::new(),
+            next_request_id: AtomicU64::new(0),
+            transport: SpinLock::new(transport),
+        });
+
+        let mut transport = device.transport.disable_irq().lock();
+        let handle_hiprio = {
+            let device = device.clone();
+            move |_: &TrapFrame| device.handle_hiprio_irq()
+        };
+        let handle_request = {
+            let device = device.clone();
+            move |_: &TrapFrame| device.handle_request_irq()
+        };
+        transport
+            .register_queue_callback(QUEUE_HIPRIO, Box::new(handle_hiprio), false)
+            .unwrap();
+        transport
+            .register_queue_callback(QUEUE_REQUEST, Box::new(handle_request), false)
+            .unwrap();
+        transport
+            .register_cfg_callback(Box::new(config_space_change))
+            .unwrap();
+        transport.finish_init();
+        drop(transport);
+
+        if tag.is_empty() {
+            warn!("[Virtio-FS]: Found a device without a tag");
+        }
+        info!("[Virtio-FS]: Found a device with the tag {:?}", tag);
+        register_device(tag, device);
+
+        Ok(())
+    }
+
+    /// Sends a request and waits for its reply.
+    ///
+    /// The reply is at most `max_reply_len` bytes. The current thread is blocked
+    /// uninterruptibly until the device replies.
+    pub fn request(
+        &self,
+        request: &[u8],
+        max_reply_len: usize,
+    ) -> Result<Vec<u8>, VirtioDeviceError> {
+        let request_dma = alloc_dma(request.len())?;
+        request_dma.write_bytes(0, request).unwrap();
+        request_dma.sync_to_device(0..request.len()).unwrap();
+        let request_slice = Slice::new(&request_dma, 0..request.len());
+
+        let reply_dma = alloc_dma(max_reply_len)?;
+        let reply_slice = Slice::new(&reply_dma, 0..max_reply_len);
+
+        let id = self.next_request_id.fetch_add(1, Ordering::Relaxed);
+        loop {
+            let mut queue = self.request_queue.disable_irq().lock();
+            if queue.available_desc() < 2 {
+                drop(queue);
+                spin_loop();
+                continue;
+            }
+            let token = queue.add_dma_buf(&[&request_slice], &[&reply_slice])?;
+            // Records the request before the device may complete it.
+            self.submitted_requests
+                .disable_irq()
+                .lock()
+                .insert(token, id);
+            if queue.should_notify() {
+                queue.notify();
+            }
+            break;
+        }
+
+        let len = self
+            .wait_queue
+            .wait_until(|| self.completed_requests.disable_irq().lock().remove(&id))
+            as usize;
+        let len = len.min(max_reply_len);
+
+        reply_dma.sync_from_device(0..len).unwrap();
+        let mut reply = vec![0u8; len];
+        reply_dma.read_bytes(0, reply.as_mut_slice()).unwrap();
+        Ok(reply)
+    }
+
+    /// Sends a request without a reply to the high-priority queue.
+    ///
+    /// This method returns without waiting for the device to process the request.
+    pub fn send_hiprio(&self, request: &[u8]) -> Result<(), VirtioDeviceError> {
+        let request_dma = Arc::new(alloc_dma(request.len())?);
+        request_dma.write_bytes(0, request).unwrap();
+        request_dma.sync_to_device(0..request.len()).unwrap();
+        let request_slice = Slice::new(request_dma.clone(), 0..request.len());
+
+        loop {
+            let mut queue = self.hiprio_queue.disable_irq().lock();
+            if queue.available_desc() < 1 {
+                drop(queue);
+                spin_loop();
+                continue;
+            }
+            let token = queue.add_dma_buf(&[&request_slice], &[])?;
+            // The buffer must live until the device finishes reading it.
+            self.hiprio_buffers
+                .disable_irq()
+                .lock()
+                .insert(token, request_dma);
+            if queue.should_notify() {
+                queue.notify();
+            }
+            return Ok(());
+        }
+    }
+
+    fn handle_hiprio_irq(&self) {
+        let mut queue = self.hiprio_queue.lock();
+        while let Ok((token, _)) = queue.pop_used() {
+            self.hiprio_buffers.lock().remove(&token);
+        }
+    }
+
+    fn handle_request_irq(&self) {
+        let mut queue = self.request_queue.lock();
+        let mut completed_requests = self.completed_requests.lock();
+        while let Ok((token, len)) = queue.pop_used() {
+            let Some(id) = self.submitted_requests.lock().remove(&token) else {
+                continue;
+            };
+            completed_requests.insert(id, len);
+        }
+        drop(completed_requests);
+        drop(queue);
+
+        self.wait_queue.wake_all();
+    }
+}
+
+impl Debug for FileSystemDevice {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FileSystemDevice")
+            .field("config", &self.config_manager.read_config())
+            .field("hiprio_queue", &self.hiprio_queue)
+            .field("request_queue", &self.request_queue)
+            .field("transport", &self.transport)
+            .finish()
+    }
+}
+
+/// Allocates a DMA buffer that has at least `len` bytes.
+fn alloc_dma(len: usize) -> Result<DmaStream, VirtioDeviceError> {
+    let nframes = len.div_ceil(PAGE_SIZE).max(1);
+    DmaStream::alloc(nframes, false).map_err(|_| VirtioDeviceError::ResourceAllocError)
+}
+
+fn config_space_change(_: &TrapFrame) {
+    debug!("Virtio-FS device config space change");
+}
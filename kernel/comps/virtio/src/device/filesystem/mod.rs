@@ -0,0 +1,49 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The virtio filesystem device, which shares a directory of the host with the guest.
+//!
+//! The requests to the device are the messages of the FUSE protocol. This module only
+//! delivers the messages, which are built and parsed by the FUSE implementation of the
+//! kernel.
+
+use alloc::{collections::BTreeMap, string::String, sync::Arc, vec::Vec};
+
+use ostd::sync::SpinLock;
+use spin::Once;
+
+use self::device::FileSystemDevice;
+
+mod config;
+pub mod device;
+
+pub const DEVICE_NAME: &str = "Virtio-FS";
+
+/// Registers a device with its tag.
+pub fn register_device(tag: String, device: Arc<FileSystemDevice>) {
+    FS_DEVICE_TABLE
+        .get()
+        .unwrap()
+        .disable_irq()
+        .lock()
+        .insert(tag, device);
+}
+
+/// Returns the device with the tag.
+pub fn get_device(tag: &str) -> Option<Arc<FileSystemDevice>> {
+    let lock = FS_DEVICE_TABLE.get().unwrap().disable_irq().lock();
+    lock.get(tag).cloned()
+}
+
+pub fn all_devices() -> Vec<(String, Arc<FileSystemDevice>)> {
+    let fs_devs = FS_DEVICE_TABLE.get().unwrap().disable_irq().lock();
+    fs_devs
+        .iter()
+        .map(|(tag, device)| (tag.clone(), device.clone()))
+        .collect()
+}
+
+pub(crate) fn init() {
+    FS_DEVICE_TABLE.call_once(|| SpinLock::new(BTreeMap::new()));
+}
+
+static FS_DEVICE_TABLE: Once<SpinLock<BTreeMap<String, Arc<FileSystemDevice>>>> = Once::new();
@@ -0,0 +1,74 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::mem::offset_of;
+
+use aster_util::safe_ptr::SafePtr;
+use ostd_pod::FromZeros;
+
+use crate::transport::{ConfigManager, VirtioTransport};
+
+bitflags::bitflags! {
+    pub(super) struct GpuFeatures: u64 {
+        /// Virgl 3D mode is supported.
+        const VIRTIO_GPU_F_VIRGL = 1 << 0;
+        /// EDID is supported.
+        const VIRTIO_GPU_F_EDID = 1 << 1;
+        /// Assigning resources UUIDs for export to other virtio devices is supported.
+        const VIRTIO_GPU_F_RESOURCE_UUID = 1 << 2;
+        /// Creating and using size-based blob resources is supported.
+        const VIRTIO_GPU_F_RESOURCE_BLOB = 1 << 3;
+        /// Multiple context types and synchronization timelines are supported.
+        const VIRTIO_GPU_F_CONTEXT_INIT = 1 << 4;
+    }
+}
+
+bitflags::bitflags! {
+    /// The pending events of the device.
+    pub(super) struct GpuEvents: u32 {
+        /// The display configuration has changed.
+        const VIRTIO_GPU_EVENT_DISPLAY = 1 << 0;
+    }
+}
+
+#[derive(Debug, Pod, Clone, Copy)]
+#[repr(C)]
+pub(super) struct VirtioGpuConfig {
+    pub events_read: u32,
+    pub events_clear: u32,
+    pub num_scanouts: u32,
+    pub num_capsets: u32,
+}
+
+impl VirtioGpuConfig {
+    pub(super) fn new_manager(transport: &dyn VirtioTransport) -> ConfigManager<Self> {
+        let safe_ptr = transport
+            .device_config_mem()
+            .map(|mem| SafePtr::new(mem, 0));
+        let bar_space = transport.device_config_bar();
+        ConfigManager::new(safe_ptr, bar_space)
+    }
+}
+
+impl ConfigManager<VirtioGpuConfig> {
+    pub(super) fn read_config(&self) -> VirtioGpuConfig {
+        let mut gpu_config = VirtioGpuConfig::new_zeroed();
+
+        gpu_config.events_read = self
+            .read_once::<u32>(offset_of!(VirtioGpuConfig, events_read))
+            .unwrap();
+        gpu_config.num_scanouts = self
+            .read_once::<u32>(offset_of!(VirtioGpuConfig, num_scanouts))
+            .unwrap();
+        gpu_config.num_capsets = self
+            .read_once::<u32>(offset_of!(VirtioGpuConfig, num_capsets))
+            .unwrap();
+
+        gpu_config
+    }
+
+    /// Acknowledges the events so that the device can clear them.
+    pub(super) fn clear_events(&self, events: GpuEvents) {
+        self.write_once(offset_of!(VirtioGpuConfig, events_clear), events.bits())
+            .unwrap();
+    }
+}
@@ -0,0 +1,336 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use core::fmt::Debug;
+
+use aster_util::mem_obj_slice::Slice;
+use id_alloc::IdAlloc;
+use log::{debug, info};
+use ostd::{
+    arch::trap::TrapFrame,
+    mm::{Daddr, PAGE_SIZE, dma::DmaStream, io::util::HasVmReaderWriter},
+    sync::{Mutex, SpinLock, WaitQueue},
+};
+use ostd_pod::{IntoBytes, Pod};
+
+use super::{
+    GpuError, Scanout,
+    config::{GpuEvents, VirtioGpuConfig},
+    protocol::{
+        CtrlHeader, CtrlType, MemEntry, Rect, ResourceAttachBacking, ResourceCreate2d,
+        ResourceDetachBacking, ResourceFlush, ResourceFormat, ResourceUnref, RespDisplayInfo,
+        SetScanout, TransferToHost2d, VIRTIO_GPU_MAX_SCANOUTS,
+    },
+    register_device,
+};
+use crate::{
+    device::VirtioDeviceError,
+    queue::VirtQueue,
+    transport::{ConfigManager, VirtioTransport},
+};
+
+/// The maximum number of resources that can be created at the same time.
+const MAX_NR_RESOURCES: usize = 1024;
+
+/// The size of the buffers that hold a command and its response.
+const COMMAND_BUFFER_SIZE: usize = PAGE_SIZE;
+
+/// The virtio GPU device.
+///
+/// Only the 2D operations are supported. The commands are sent one at a time, and the caller
+/// sleeps until the device responds.
+pub struct GpuDevice {
+    config_manager: ConfigManager<VirtioGpuConfig>,
+    transport: SpinLock<Box<dyn VirtioTransport>>,
+    control_queue: SpinLock<VirtQueue>,
+    request_buffer: DmaStream,
+    response_buffer: DmaStream,
+    /// The lock that serializes the commands, because there is only one pair of buffers.
+    command_lock: Mutex<()>,
+    wait_queue: WaitQueue,
+    /// The allocator of the resource IDs, where resource ID `n + 1` is allocated as `n`.
+    resource_ids: SpinLock<IdAlloc>,
+}
+
+impl Debug for GpuDevice {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("GpuDevice")
+            .field("config", &self.config_manager.read_config())
+            .field("transport", &self.transport)
+            .finish_non_exhaustive()
+    }
+}
+
+impl GpuDevice {
+    pub(crate) fn negotiate_features(_features: u64) -> u64 {
+        // Neither 3D nor EDID is supported yet.
+        0
+    }
+
+    pub(crate) fn init(mut transport: Box<dyn VirtioTransport>) -> Result<(), VirtioDeviceError> {
+        // The cursor queue (queue 1) is not used, since the cursor is not supported yet.
+        const CONTROL_QUEUE_INDEX: u16 = 0;
+
+        let config_manager = VirtioGpuConfig::new_manager(transport.as_ref());
+        debug!("virtio_gpu_config = {:?}", config_manager.read_config());
+
+        let control_queue = VirtQueue::new(CONTROL_QUEUE_INDEX, 2, transport.as_mut())?;
+
+        let request_buffer = DmaStream::alloc(COMMAND_BUFFER_SIZE / PAGE_SIZE, false)
+            .map_err(|_| VirtioDeviceError::ResourceAllocError)?;
+        let response_buffer = DmaStream::alloc(COMMAND_BUFFER_SIZE / PAGE_SIZE, false)
+            .map_err(|_| VirtioDeviceError::ResourceAllocError)?;
+
+        let device = Arc::new(Self {
+            config_manager,
+            transport: SpinLock::new(transport),
+            control_queue: SpinLock::new(control_queue),
+            request_buffer,
+            response_buffer,
+            command_lock: Mutex::new(()),
+            wait_queue: WaitQueue::new(),
+            resource_ids: SpinLock::new(IdAlloc::with_capacity(MAX_NR_RESOURCES)),
+        });
+
+        // Register irq callbacks
+        let mut transport = device.transport.disable_irq().lock();
+        let handle_response = {
+            let device = device.clone();
+            move |_: &TrapFrame| device.wait_queue.wake_all()
+        };
+        transport
+            .register_queue_callback(CONTROL_QUEUE_INDEX, Box::new(handle_response), false)
+            .unwrap();
+        let handle_config_change = {
+            let device = device.clone();
+            move |_: &TrapFrame| device.handle_config_change()
+        };
+        transport
+            .register_cfg_callback(Box::new(handle_config_change))
+            .unwrap();
+        transport.finish_init();
+        drop(transport);
+
+        register_device(device);
+
+        Ok(())
+    }
+
+    fn handle_config_change(&self) {
+        let events = GpuEvents::from_bits_truncate(self.config_manager.read_config().events_read);
+        if events.contains(GpuEvents::VIRTIO_GPU_EVENT_DISPLAY) {
+            // TODO: Notify the users so that they can query the new display information.
+            info!("Virtio-GPU: the display configuration has changed");
+        }
+        self.config_manager.clear_events(events);
+    }
+
+    /// Returns the number of scanouts.
+    pub fn num_scanouts(&self) -> u32 {
+        self.config_manager
+            .read_config()
+            .num_scanouts
+            .min(VIRTIO_GPU_MAX_SCANOUTS as u32)
+    }
+
+    /// Returns the display information of the scanouts.
+    pub fn display_info(&self) -> Result<Vec<Scanout>, GpuError> {
+        let request = CtrlHeader::new(CtrlType::GetDisplayInfo);
+        let response: RespDisplayInfo =
+            self.send_command(&request, &[], CtrlType::OkDisplayInfo)?;
+
+        let scanouts = response.pmodes[..self.num_scanouts() as usize]
+            .iter()
+            .map(|pmode| Scanout {
+                width: pmode.rect.width,
+                height: pmode.rect.height,
+                is_enabled: pmode.enabled != 0,
+            })
+            .collect();
+        Ok(scanouts)
+    }
+
+    /// Creates a 2D resource on the host and returns its ID.
+    pub fn create_resource_2d(
+        &self,
+        format: ResourceFormat,
+        width: u32,
+        height: u32,
+    ) -> Result<u32, GpuError> {
+        let resource_id = self
+            .resource_ids
+            .disable_irq()
+            .lock()
+            .alloc()
+            .ok_or(GpuError::OutOfMemory)? as u32
+            + 1;
+
+        let request = ResourceCreate2d {
+            header: CtrlHeader::new(CtrlType::ResourceCreate2d),
+            resource_id,
+            format: format as u32,
+            width,
+            height,
+        };
+        if let Err(err) = self.send_command_nodata(&request, &[]) {
+            self.free_resource_id(resource_id);
+            return Err(err);
+        }
+
+        Ok(resource_id)
+    }
+
+    /// Destroys a resource on the host.
+    pub fn unref_resource(&self, resource_id: u32) -> Result<(), GpuError> {
+        let request = ResourceUnref {
+            header: CtrlHeader::new(CtrlType::ResourceUnref),
+            resource_id,
+            padding: 0,
+        };
+        self.send_command_nodata(&request, &[])?;
+        self.free_resource_id(resource_id);
+        Ok(())
+    }
+
+    fn free_resource_id(&self, resource_id: u32) {
+        self.resource_ids
+            .disable_irq()
+            .lock()
+            .free(resource_id as usize - 1);
+    }
+
+    /// Attaches the guest memory to a resource as its backing storage.
+    ///
+    /// The memory is described as a list of the device addresses and the lengths.
+    pub fn attach_backing(
+        &self,
+        resource_id: u32,
+        entries: &[(Daddr, usize)],
+    ) -> Result<(), GpuError> {
+        let request = ResourceAttachBacking {
+            header: CtrlHeader::new(CtrlType::ResourceAttachBacking),
+            resource_id,
+            nr_entries: entries.len() as u32,
+        };
+        let entries: Vec<MemEntry> = entries
+            .iter()
+            .map(|(addr, length)| MemEntry {
+                addr: *addr as u64,
+                length: *length as u32,
+                padding: 0,
+            })
+            .collect();
+        self.send_command_nodata(&request, entries.as_bytes())
+    }
+
+    /// Detaches the backing storage from a resource.
+    pub fn detach_backing(&self, resource_id: u32) -> Result<(), GpuError> {
+        let request = ResourceDetachBacking {
+            header: CtrlHeader::new(CtrlType::ResourceDetachBacking),
+            resource_id,
+            padding: 0,
+        };
+        self.send_command_nodata(&request, &[])
+    }
+
+    /// Sets the resource to be displayed on a scanout.
+    ///
+    /// If `resource_id` is zero, the scanout is disabled.
+    pub fn set_scanout(
+        &self,
+        scanout_id: u32,
+        resource_id: u32,
+        rect: Rect,
+    ) -> Result<(), GpuError> {
+        let request = SetScanout {
+            header: CtrlHeader::new(CtrlType::SetScanout),
+            rect,
+            scanout_id,
+            resource_id,
+        };
+        self.send_command_nodata(&request, &[])
+    }
+
+    /// Copies a region from the backing storage of a resource to the resource on the host.
+    ///
+    /// `offset` is the offset of the region in the backing storage.
+    pub fn transfer_to_host_2d(
+        &self,
+        resource_id: u32,
+        rect: Rect,
+        offset: u64,
+    ) -> Result<(), GpuError> {
+        let request = TransferToHost2d {
+            header: CtrlHeader::new(CtrlType::TransferToHost2d),
+            rect,
+            offset,
+            resource_id,
+            padding: 0,
+        };
+        self.send_command_nodata(&request, &[])
+    }
+
+    /// Flushes a region of a resource to the scanouts that display it.
+    pub fn flush_resource(&self, resource_id: u32, rect: Rect) -> Result<(), GpuError> {
+        let request = ResourceFlush {
+            header: CtrlHeader::new(CtrlType::ResourceFlush),
+            rect,
+            resource_id,
+            padding: 0,
+        };
+        self.send_command_nodata(&request, &[])
+    }
+
+    fn send_command_nodata<Req: Pod>(&self, request: &Req, payload: &[u8]) -> Result<(), GpuError> {
+        let _: CtrlHeader = self.send_command(request, payload, CtrlType::OkNodata)?;
+        Ok(())
+    }
+
+    /// Sends a command followed by the payload and waits for the response.
+    fn send_command<Req: Pod, Resp: Pod>(
+        &self,
+        request: &Req,
+        payload: &[u8],
+        expected_type: CtrlType,
+    ) -> Result<Resp, GpuError> {
+        let request_len = size_of::<Req>() + payload.len();
+        let response_len = size_of::<Resp>();
+        if request_len > COMMAND_BUFFER_SIZE {
+            return Err(GpuError::InvalidParameter);
+        }
+
+        let _guard = self.command_lock.lock();
+
+        let mut writer = self.request_buffer.writer().unwrap();
+        writer.write_val(request).unwrap();
+        writer.write(&mut payload.into());
+        self.request_buffer.sync_to_device(0..request_len).unwrap();
+
+        let request_slice = Slice::new(&self.request_buffer, 0..request_len);
+        let response_slice = Slice::new(&self.response_buffer, 0..response_len);
+        let mut control_queue = self.control_queue.disable_irq().lock();
+        control_queue
+            .add_dma_buf(&[&request_slice], &[&response_slice])
+            .unwrap();
+        if control_queue.should_notify() {
+            control_queue.notify();
+        }
+        drop(control_queue);
+
+        self.wait_queue.wait_until(|| {
+            let mut control_queue = self.control_queue.disable_irq().lock();
+            control_queue.pop_used().ok()
+        });
+
+        self.response_buffer
+            .sync_from_device(0..response_len)
+            .unwrap();
+        let mut reader = self.response_buffer.reader().unwrap();
+        let header: CtrlHeader = reader.clone().read_val().unwrap();
+        match CtrlType::try_from(header.type_) {
+            Ok(type_) if type_ == expected_type => Ok(reader.read_val().unwrap()),
+            Ok(type_) => Err(GpuError::from(type_)),
+            Err(_) => Err(GpuError::Unspecified),
+        }
+    }
+}
@@ -0,0 +1,64 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The virtio GPU device, which provides the scanouts (i.e., displays) of the virtual machine.
+//!
+//! Only the 2D mode is supported. The kernel creates resources on the host, attaches the guest
+//! memory to them as their backing storage, and displays them on the scanouts.
+
+use alloc::{sync::Arc, vec::Vec};
+
+use ostd::sync::SpinLock;
+
+pub use self::protocol::{Rect, ResourceFormat};
+use self::{device::GpuDevice, protocol::CtrlType};
+
+mod config;
+pub mod device;
+mod protocol;
+
+/// The display information of a scanout.
+#[derive(Debug, Clone, Copy)]
+pub struct Scanout {
+    /// The preferred width of the display
+    pub width: u32,
+    /// The preferred height of the display
+    pub height: u32,
+    /// Whether the display is enabled by the user (e.g., the window is open)
+    pub is_enabled: bool,
+}
+
+/// The errors reported by the device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpuError {
+    Unspecified,
+    OutOfMemory,
+    InvalidScanoutId,
+    InvalidResourceId,
+    InvalidContextId,
+    InvalidParameter,
+}
+
+impl From<CtrlType> for GpuError {
+    fn from(type_: CtrlType) -> Self {
+        match type_ {
+            CtrlType::ErrOutOfMemory => Self::OutOfMemory,
+            CtrlType::ErrInvalidScanoutId => Self::InvalidScanoutId,
+            CtrlType::ErrInvalidResourceId => Self::InvalidResourceId,
+            CtrlType::ErrInvalidContextId => Self::InvalidContextId,
+            CtrlType::ErrInvalidParameter => Self::InvalidParameter,
+            _ => Self::Unspecified,
+        }
+    }
+}
+
+/// Registers a device.
+pub fn register_device(device: Arc<GpuDevice>) {
+    GPU_DEVICES.lock().push(device);
+}
+
+/// Returns all the devices in the order in which they are found.
+pub fn all_devices() -> Vec<Arc<GpuDevice>> {
+    GPU_DEVICES.lock().clone()
+}
+
+static GPU_DEVICES: SpinLock<Vec<Arc<GpuDevice>>> = SpinLock::new(Vec::new());
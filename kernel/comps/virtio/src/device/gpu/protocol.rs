@@ -0,0 +1,201 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The commands and responses that are exchanged over the control virtqueue.
+//!
+//! Only the commands for 2D operations are defined here.
+//!
+//! Reference: The VirtIO spec 5.7.6 Device Operation.
+
+use int_to_c_enum::TryFromInt;
+
+/// The maximum number of scanouts.
+pub(super) const VIRTIO_GPU_MAX_SCANOUTS: usize = 16;
+
+/// The type of a command or a response.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+pub(super) enum CtrlType {
+    // 2D commands
+    GetDisplayInfo = 0x0100,
+    ResourceCreate2d = 0x0101,
+    ResourceUnref = 0x0102,
+    SetScanout = 0x0103,
+    ResourceFlush = 0x0104,
+    TransferToHost2d = 0x0105,
+    ResourceAttachBacking = 0x0106,
+    ResourceDetachBacking = 0x0107,
+
+    // Success responses
+    OkNodata = 0x1100,
+    OkDisplayInfo = 0x1101,
+
+    // Error responses
+    ErrUnspec = 0x1200,
+    ErrOutOfMemory = 0x1201,
+    ErrInvalidScanoutId = 0x1202,
+    ErrInvalidResourceId = 0x1203,
+    ErrInvalidContextId = 0x1204,
+    ErrInvalidParameter = 0x1205,
+}
+
+/// The header of all commands and responses; `struct virtio_gpu_ctrl_hdr` in the spec.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+pub(super) struct CtrlHeader {
+    pub(super) type_: u32,
+    pub(super) flags: u32,
+    pub(super) fence_id: u64,
+    pub(super) ctx_id: u32,
+    pub(super) ring_idx: u8,
+    pub(super) padding: [u8; 3],
+}
+
+impl CtrlHeader {
+    pub(super) fn new(type_: CtrlType) -> Self {
+        Self {
+            type_: type_ as u32,
+            flags: 0,
+            fence_id: 0,
+            ctx_id: 0,
+            ring_idx: 0,
+            padding: [0; 3],
+        }
+    }
+}
+
+/// A rectangle; `struct virtio_gpu_rect` in the spec.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Pod)]
+#[repr(C)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    /// Creates a rectangle.
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+/// The pixel formats of resources; `enum virtio_gpu_formats` in the spec.
+///
+/// The names describe the byte order in memory, e.g., the first byte of a `B8G8R8X8Unorm` pixel
+/// is blue.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceFormat {
+    B8G8R8A8Unorm = 1,
+    B8G8R8X8Unorm = 2,
+    A8R8G8B8Unorm = 3,
+    X8R8G8B8Unorm = 4,
+    R8G8B8A8Unorm = 67,
+    X8B8G8R8Unorm = 68,
+    A8B8G8R8Unorm = 121,
+    R8G8B8X8Unorm = 134,
+}
+
+/// The display information of a scanout; `struct virtio_gpu_display_one` in the spec.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+pub(super) struct DisplayOne {
+    pub(super) rect: Rect,
+    pub(super) enabled: u32,
+    pub(super) flags: u32,
+}
+
+/// The response to `VIRTIO_GPU_CMD_GET_DISPLAY_INFO`; `struct virtio_gpu_resp_display_info` in
+/// the spec.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+pub(super) struct RespDisplayInfo {
+    pub(super) header: CtrlHeader,
+    pub(super) pmodes: [DisplayOne; VIRTIO_GPU_MAX_SCANOUTS],
+}
+
+/// `struct virtio_gpu_resource_create_2d` in the spec.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+pub(super) struct ResourceCreate2d {
+    pub(super) header: CtrlHeader,
+    pub(super) resource_id: u32,
+    pub(super) format: u32,
+    pub(super) width: u32,
+    pub(super) height: u32,
+}
+
+/// `struct virtio_gpu_resource_unref` in the spec.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+pub(super) struct ResourceUnref {
+    pub(super) header: CtrlHeader,
+    pub(super) resource_id: u32,
+    pub(super) padding: u32,
+}
+
+/// `struct virtio_gpu_set_scanout` in the spec.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+pub(super) struct SetScanout {
+    pub(super) header: CtrlHeader,
+    pub(super) rect: Rect,
+    pub(super) scanout_id: u32,
+    pub(super) resource_id: u32,
+}
+
+/// `struct virtio_gpu_resource_flush` in the spec.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+pub(super) struct ResourceFlush {
+    pub(super) header: CtrlHeader,
+    pub(super) rect: Rect,
+    pub(super) resource_id: u32,
+    pub(super) padding: u32,
+}
+
+/// `struct virtio_gpu_transfer_to_host_2d` in the spec.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+pub(super) struct TransferToHost2d {
+    pub(super) header: CtrlHeader,
+    pub(super) rect: Rect,
+    pub(super) offset: u64,
+    pub(super) resource_id: u32,
+    pub(super) padding: u32,
+}
+
+/// `struct virtio_gpu_resource_attach_backing` in the spec.
+///
+/// The command is followed by `nr_entries` instances of [`MemEntry`].
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+pub(super) struct ResourceAttachBacking {
+    pub(super) header: CtrlHeader,
+    pub(super) resource_id: u32,
+    pub(super) nr_entries: u32,
+}
+
+/// `struct virtio_gpu_mem_entry` in the spec.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+pub(super) struct MemEntry {
+    pub(super) addr: u64,
+    pub(super) length: u32,
+    pub(super) padding: u32,
+}
+
+/// `struct virtio_gpu_resource_detach_backing` in the spec.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+pub(super) struct ResourceDetachBacking {
+    pub(super) header: CtrlHeader,
+    pub(super) resource_id: u32,
+    pub(super) padding: u32,
+}
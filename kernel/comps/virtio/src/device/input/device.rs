@@ -2,21 +2,24 @@
 
 use alloc::{
     boxed::Box,
+    format,
     string::{String, ToString},
     sync::Arc,
     vec::Vec,
 };
-use core::fmt::Debug;
+use core::{
+    fmt::Debug,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 use aster_input::{
-    event_type_codes::{EventTypes, KeyCode, KeyStatus, RelCode, SynEvent},
+    event_type_codes::{AbsCode, EventTypes, InputProps, KeyCode, KeyStatus, RelCode, SynEvent},
     input_dev::{
-        InputCapability, InputDevice as InputDeviceTrait, InputEvent, InputId,
+        AbsInfo, InputCapability, InputDevice as InputDeviceTrait, InputEvent, InputId,
         RegisteredInputDevice,
     },
 };
 use aster_util::{field_ptr, safe_ptr::SafePtr};
-use bitflags::bitflags;
 use log::{debug, info};
 use ostd::{
     arch::trap::TrapFrame,
@@ -24,37 +27,23 @@ use ostd::{
     mm::{HasDaddr, PAGE_SIZE, dma::DmaStream},
     sync::SpinLock,
 };
-use ostd_pod::IntoBytes;
+use ostd_pod::{IntoBytes, Pod};
 
-use super::{InputConfigSelect, QUEUE_EVENT, QUEUE_STATUS, VirtioInputConfig, VirtioInputEvent};
+use super::{
+    AbsInfo as VirtioAbsInfo, DevIds, InputConfigSelect, QUEUE_EVENT, QUEUE_STATUS,
+    VirtioInputConfig, VirtioInputEvent,
+};
 use crate::{
     device::VirtioDeviceError, dma_buf::DmaBuf, queue::VirtQueue, transport::VirtioTransport,
 };
 
-bitflags! {
-    /// The properties of input device.
-    ///
-    /// Ref: Linux input-event-codes.h
-    struct InputProp : u8 {
-        /// Needs a pointer
-        const POINTER           = 1 << 0;
-        /// Direct input devices
-        const DIRECT            = 1 << 1;
-        /// Has button(s) under pad
-        const BUTTONPAD         = 1 << 2;
-        /// Touch rectangle only
-        const SEMI_MT           = 1 << 3;
-        /// Softbuttons at top of pad
-        const TOPBUTTONPAD      = 1 << 4;
-        /// Is a pointing stick
-        const POINTING_STICK    = 1 << 5;
-        /// Has accelerometer
-        const ACCELEROMETER     = 1 << 6;
-    }
-}
-
 const QUEUE_SIZE: u16 = 64;
 
+/// The number of virtio input devices that have been probed.
+///
+/// This is used to give each device a distinct physical path.
+static DEVICE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
 /// Virtual human interface devices such as keyboards, mice and tablets.
 ///
 /// An instance of the virtio device represents one such input device.
@@ -96,6 +85,8 @@ impl InputDevice {
             }
         }
 
+        let index = DEVICE_COUNT.fetch_add(1, Ordering::Relaxed);
+
         let device = {
             let mut device = Self {
                 config: VirtioInputConfig::new(transport.as_mut()),
@@ -106,7 +97,7 @@ impl InputDevice {
                 // Default name, will be updated with actual device name from config.
                 device_name: "virtio_input".to_string(),
                 // Physical path for virtio devices.
-                device_phys: "virtio/input0".to_string(),
+                device_phys: format!("virtio{}/input0", index),
                 // Unique identifier (empty for virtio devices).
                 device_uniq: "".to_string(),
                 // Device ID with virtio-specific values.
@@ -123,19 +114,17 @@ impl InputDevice {
             info!("Virtio input device name: {}", name);
             device.device_name = name;
 
+            // Query and update device ID from config.
+            if let Some(device_id) = device.query_config_dev_ids() {
+                device.device_id = device_id;
+            }
+
             // Query and set device capabilities.
             device.query_and_set_capabilities();
 
             Arc::new(device)
         };
 
-        let input_prop = device.query_config_prop_bits();
-        if let Some(prop) = input_prop {
-            debug!("input device prop: {:?}", prop);
-        } else {
-            debug!("input device has no properties or the properties is not defined");
-        }
-
         let mut transport = device.transport.disable_irq().lock();
         fn config_space_change(_: &TrapFrame) {
             debug!("input device config space change");
@@ -185,30 +174,63 @@ impl InputDevice {
 
     fn query_config_id_name(&self) -> String {
         let size = self.select_config(InputConfigSelect::IdName, 0);
-
-        let out = {
-            // TODO: Add a general API to read this byte-by-byte.
-            let mut out = Vec::with_capacity(size);
-            let mut data_ptr = field_ptr!(&self.config, VirtioInputConfig, data).cast::<u8>();
-            for _ in 0..size {
-                out.push(data_ptr.read_once().unwrap());
-                data_ptr.byte_add(1);
-            }
-            out
-        };
+        let out = self.read_config_data(size);
 
         String::from_utf8(out).unwrap()
     }
 
-    fn query_config_prop_bits(&self) -> Option<InputProp> {
+    fn query_config_dev_ids(&self) -> Option<InputId> {
+        let size = self.select_config(InputConfigSelect::IdDevids, 0);
+        if size < size_of::<DevIds>() {
+            return None;
+        }
+
+        let dev_ids = DevIds::from_first_bytes(&self.read_config_data(size));
+        Some(InputId::new(
+            dev_ids.bustype,
+            dev_ids.vendor,
+            dev_ids.product,
+            dev_ids.version,
+        ))
+    }
+
+    fn query_config_prop_bits(&self) -> InputProps {
         let size = self.select_config(InputConfigSelect::PropBits, 0);
-        if size == 0 {
+
+        let mut bits = 0u32;
+        for (i, byte) in self.read_config_data(size.min(4)).into_iter().enumerate() {
+            bits |= (byte as u32) << (i * 8);
+        }
+        InputProps::from_bits_truncate(bits)
+    }
+
+    fn query_config_abs_info(&self, abs_code: AbsCode) -> Option<AbsInfo> {
+        let size = self.select_config(InputConfigSelect::AbsInfo, abs_code as u8);
+        if size < size_of::<VirtioAbsInfo>() {
             return None;
         }
-        assert!(size == 1);
 
-        let data_ptr = field_ptr!(&self.config, VirtioInputConfig, data);
-        InputProp::from_bits(data_ptr.cast::<u8>().read_once().unwrap())
+        let abs_info = VirtioAbsInfo::from_first_bytes(&self.read_config_data(size));
+        Some(AbsInfo {
+            value: 0,
+            minimum: abs_info.min as i32,
+            maximum: abs_info.max as i32,
+            fuzz: abs_info.fuzz as i32,
+            flat: abs_info.flat as i32,
+            resolution: abs_info.res as i32,
+        })
+    }
+
+    /// Reads the first `size` bytes of the data returned by the last `select_config`.
+    fn read_config_data(&self, size: usize) -> Vec<u8> {
+        // TODO: Add a general API to read this byte-by-byte.
+        let mut out = Vec::with_capacity(size);
+        let mut data_ptr = field_ptr!(&self.config, VirtioInputConfig, data).cast::<u8>();
+        for _ in 0..size {
+            out.push(data_ptr.read_once().unwrap());
+            data_ptr.byte_add(1);
+        }
+        out
     }
 
     /// Query a specific piece of information by `select` and `subsel`, return the result size.
@@ -278,6 +300,20 @@ impl InputDevice {
                 }
             }
 
+            // Absolute position events (EV_ABS)
+            3 => {
+                if let Some(abs_code) = map_to_abs_code(virtio_event.code) {
+                    let abs_value = virtio_event.value as i32;
+                    let abs_event = InputEvent::from_absolute_position(abs_code, abs_value);
+                    registered_device.submit_events(&[abs_event]);
+                } else {
+                    debug!(
+                        "VirtIO Input: unmapped absolute event code {}, dropped",
+                        virtio_event.code
+                    );
+                }
+            }
+
             // Other event types
             _ => {
                 debug!(
@@ -406,6 +442,13 @@ fn map_to_key_code(virtio_code: u16) -> Option<KeyCode> {
         125 => KeyCode::LeftMeta,
         126 => KeyCode::RightMeta,
         139 => KeyCode::Menu,
+        0x110 => KeyCode::BtnLeft,
+        0x111 => KeyCode::BtnRight,
+        0x112 => KeyCode::BtnMiddle,
+        0x113 => KeyCode::BtnSide,
+        0x114 => KeyCode::BtnExtra,
+        0x115 => KeyCode::BtnForward,
+        0x116 => KeyCode::BtnBack,
         _ => return None,
     })
 }
@@ -430,6 +473,55 @@ fn map_to_rel_code(virtio_code: u16) -> Option<RelCode> {
     })
 }
 
+/// Maps a VirtIO absolute axis code to an [`AbsCode`].
+fn map_to_abs_code(virtio_code: u16) -> Option<AbsCode> {
+    Some(match virtio_code {
+        0x00 => AbsCode::X,
+        0x01 => AbsCode::Y,
+        0x02 => AbsCode::Z,
+        0x03 => AbsCode::Rx,
+        0x04 => AbsCode::Ry,
+        0x05 => AbsCode::Rz,
+        0x06 => AbsCode::Throttle,
+        0x07 => AbsCode::Rudder,
+        0x08 => AbsCode::Wheel,
+        0x09 => AbsCode::Gas,
+        0x0a => AbsCode::Brake,
+        0x10 => AbsCode::Hat0X,
+        0x11 => AbsCode::Hat0Y,
+        0x12 => AbsCode::Hat1X,
+        0x13 => AbsCode::Hat1Y,
+        0x14 => AbsCode::Hat2X,
+        0x15 => AbsCode::Hat2Y,
+        0x16 => AbsCode::Hat3X,
+        0x17 => AbsCode::Hat3Y,
+        0x18 => AbsCode::Pressure,
+        0x19 => AbsCode::Distance,
+        0x1a => AbsCode::TiltX,
+        0x1b => AbsCode::TiltY,
+        0x1c => AbsCode::ToolWidth,
+        0x20 => AbsCode::Volume,
+        0x21 => AbsCode::Profile,
+        0x28 => AbsCode::Misc,
+        0x2f => AbsCode::MtSlot,
+        0x30 => AbsCode::MtTouchMajor,
+        0x31 => AbsCode::MtTouchMinor,
+        0x32 => AbsCode::MtWidthMajor,
+        0x33 => AbsCode::MtWidthMinor,
+        0x34 => AbsCode::MtOrientation,
+        0x35 => AbsCode::MtPositionX,
+        0x36 => AbsCode::MtPositionY,
+        0x37 => AbsCode::MtToolType,
+        0x38 => AbsCode::MtBlobId,
+        0x39 => AbsCode::MtTrackingId,
+        0x3a => AbsCode::MtPressure,
+        0x3b => AbsCode::MtDistance,
+        0x3c => AbsCode::MtToolX,
+        0x3d => AbsCode::MtToolY,
+        _ => return None,
+    })
+}
+
 impl InputDeviceTrait for InputDevice {
     fn name(&self) -> &str {
         &self.device_name
@@ -458,8 +550,28 @@ impl InputDevice {
         // Query supported event types.
         let ev_key = self.query_ev_bits(EventTypes::KEY.as_index());
         let ev_rel = self.query_ev_bits(EventTypes::REL.as_index());
+        let ev_abs = self.query_ev_bits(EventTypes::ABS.as_index());
+
+        // Query the parameters of absolute axes.
+        let mut abs_infos = Vec::new();
+        if let Some(abs_bits) = &ev_abs {
+            for bit in 0..abs_bits.len() * 8 {
+                if abs_bits[bit / 8] & (1 << (bit % 8)) == 0 {
+                    continue;
+                }
+                let Some(abs_code) = map_to_abs_code(bit as u16) else {
+                    continue;
+                };
+                if let Some(abs_info) = self.query_config_abs_info(abs_code) {
+                    abs_infos.push((abs_code, abs_info));
+                }
+            }
+        }
+
+        let props = self.query_config_prop_bits();
 
         let capability = &mut self.capability;
+        capability.set_properties(props);
         capability.set_supported_event_type(EventTypes::SYN);
 
         if ev_key.is_some() {
@@ -491,10 +603,16 @@ impl InputDevice {
             }
         }
 
+        // Set absolute axis capabilities.
+        for (abs_code, abs_info) in abs_infos {
+            capability.set_supported_absolute_axis(abs_code, abs_info);
+        }
+
         info!(
-            "VirtIO input device capabilities set: KEY={}, REL={}",
+            "VirtIO input device capabilities set: KEY={}, REL={}, ABS={}",
             ev_key.is_some(),
-            ev_rel.is_some()
+            ev_rel.is_some(),
+            ev_abs.is_some()
         );
     }
 
@@ -505,14 +623,7 @@ impl InputDevice {
             return None;
         }
 
-        let mut bits = Vec::with_capacity(size);
-        let data_ptr = field_ptr!(&self.config, VirtioInputConfig, data).cast::<u8>();
-        for i in 0..size {
-            let mut ptr = data_ptr.clone();
-            ptr.byte_add(i);
-            bits.push(ptr.read_once().unwrap());
-        }
-        Some(bits)
+        Some(self.read_config_data(size))
     }
 }
 
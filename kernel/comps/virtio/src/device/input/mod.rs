@@ -48,7 +48,6 @@ enum InputConfigSelect {
     #[expect(dead_code)]
     IdSerial = 0x02,
     /// Returns ID information of the device, subsel is zero.
-    #[expect(dead_code)]
     IdDevids = 0x03,
     /// Returns input properties of the device, subsel is zero.
     /// Individual bits in the bitmap correspond to INPUT_PROP_* constants used
@@ -62,7 +61,6 @@ enum InputConfigSelect {
     EvBits = 0x11,
     /// subsel specifies the absolute axis using ABS_* constants in the underlying
     /// evdev implementation. Information about the axis will be returned.
-    #[expect(dead_code)]
     AbsInfo = 0x12,
 }
 
@@ -89,7 +87,6 @@ impl VirtioInputConfig {
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone, Pod)]
-#[expect(dead_code)]
 struct AbsInfo {
     min: u32,
     max: u32,
@@ -98,6 +95,15 @@ struct AbsInfo {
     res: u32,
 }
 
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod)]
+struct DevIds {
+    bustype: u16,
+    vendor: u16,
+    product: u16,
+    version: u16,
+}
+
 /// Both queues use the same `virtio_input_event` struct. `type`, `code` and `value`
 /// are filled according to the Linux input layer (evdev) interface.
 #[repr(C)]
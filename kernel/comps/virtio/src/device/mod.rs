@@ -6,9 +6,14 @@ use crate::queue::QueueError;
 
 pub mod block;
 pub mod console;
+pub mod entropy;
+pub mod filesystem;
+pub mod gpu;
 pub mod input;
 pub mod network;
+pub mod scsi;
 pub mod socket;
+pub mod sound;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, TryFromInt)]
 #[repr(u8)]
@@ -36,6 +41,8 @@ pub(crate) enum VirtioDeviceType {
     Pstore = 22,
     Iommu = 23,
     Memory = 24,
+    Sound = 25,
+    FileSystem = 26,
 }
 
 #[derive(Debug)]
@@ -47,6 +54,8 @@ pub enum VirtioDeviceError {
     QueueUnknownError,
     /// The input virtio capability list contains invalid element
     CapabilityListError,
+    /// Failed to allocate the resources, e.g., the DMA buffers
+    ResourceAllocError,
 }
 
 impl From<QueueError> for VirtioDeviceError {
@@ -54,3 +63,4 @@ impl From<QueueError> for VirtioDeviceError {
         VirtioDeviceError::QueueUnknownError
     }
 }
+
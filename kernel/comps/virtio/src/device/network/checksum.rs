@@ -0,0 +1,148 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Checksum offloading for packets sent to the device.
+
+use aster_bigtcp::wire::ETHERNET_HEADER_LEN;
+
+/// The position of a TCP or UDP checksum that the device should complete.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct PartialChecksum {
+    /// The offset in the frame where checksumming starts (i.e., the start of the TCP or UDP
+    /// header).
+    pub(super) start: u16,
+    /// The offset of the checksum field, relative to `start`.
+    pub(super) offset: u16,
+}
+
+/// Prepares an Ethernet frame so that its TCP or UDP checksum can be computed by the device.
+///
+/// If the frame contains an unfragmented TCP or UDP packet over IPv4 or IPv6, this method fills
+/// the checksum field with the checksum of the pseudo-header and returns the position of the
+/// checksum. Otherwise, the frame is left untouched and `None` is returned.
+///
+/// Reference: "5.1.6.2 Packet Transmission" in the virtio specification.
+pub(super) fn prepare_partial_checksum(frame: &mut [u8]) -> Option<PartialChecksum> {
+    let ip_packet = frame.get(ETHERNET_HEADER_LEN..)?;
+    let ip_header = match read_u16(frame, ETHERTYPE_OFFSET)? {
+        ETHERTYPE_IPV4 => parse_ipv4_header(ip_packet)?,
+        ETHERTYPE_IPV6 => parse_ipv6_header(ip_packet)?,
+        _ => return None,
+    };
+
+    let csum_offset = match ip_header.protocol {
+        IPPROTO_TCP => TCP_CHECKSUM_OFFSET,
+        IPPROTO_UDP => UDP_CHECKSUM_OFFSET,
+        _ => return None,
+    };
+
+    // The device computes the checksum until the end of the frame, so the frame must not contain
+    // anything (e.g., paddings) after the TCP or UDP packet.
+    let csum_start = ETHERNET_HEADER_LEN + ip_header.header_len;
+    if csum_start + ip_header.payload_len != frame.len()
+        || csum_offset + size_of::<u16>() > ip_header.payload_len
+    {
+        return None;
+    }
+
+    let csum = pseudo_header_checksum(
+        &frame[ETHERNET_HEADER_LEN + ip_header.addrs_offset..][..ip_header.addrs_len],
+        ip_header.protocol,
+        ip_header.payload_len,
+    );
+    frame[csum_start + csum_offset..][..size_of::<u16>()].copy_from_slice(&csum.to_be_bytes());
+
+    Some(PartialChecksum {
+        start: csum_start as u16,
+        offset: csum_offset as u16,
+    })
+}
+
+/// The fields of an IP header that are needed to build the pseudo-header.
+struct IpHeader {
+    header_len: usize,
+    payload_len: usize,
+    protocol: u8,
+    /// The offset of the source address, which is immediately followed by the destination
+    /// address.
+    addrs_offset: usize,
+    /// The total length of the source and destination addresses.
+    addrs_len: usize,
+}
+
+fn parse_ipv4_header(packet: &[u8]) -> Option<IpHeader> {
+    let version_ihl = *packet.first()?;
+    if version_ihl >> 4 != 4 {
+        return None;
+    }
+
+    let header_len = usize::from(version_ihl & 0x0f) * 4;
+    let total_len = usize::from(read_u16(packet, 2)?);
+    if header_len < IPV4_MIN_HEADER_LEN || total_len < header_len || total_len > packet.len() {
+        return None;
+    }
+
+    // Only the first fragment contains the TCP or UDP header, and the checksum covers all the
+    // fragments, so fragmented packets cannot be offloaded.
+    let frag = read_u16(packet, 6)?;
+    if frag & (IPV4_MORE_FRAGMENTS | IPV4_FRAGMENT_OFFSET) != 0 {
+        return None;
+    }
+
+    Some(IpHeader {
+        header_len,
+        payload_len: total_len - header_len,
+        protocol: packet[9],
+        addrs_offset: 12,
+        addrs_len: 8,
+    })
+}
+
+fn parse_ipv6_header(packet: &[u8]) -> Option<IpHeader> {
+    if packet.len() < IPV6_HEADER_LEN || packet[0] >> 4 != 6 {
+        return None;
+    }
+
+    // TODO: Support extension headers. They are not generated by our network stack, so the
+    // packets that contain them are simply not offloaded.
+    Some(IpHeader {
+        header_len: IPV6_HEADER_LEN,
+        payload_len: usize::from(read_u16(packet, 4)?),
+        protocol: packet[6],
+        addrs_offset: 8,
+        addrs_len: 32,
+    })
+}
+
+/// Computes the (non-complemented) checksum of the TCP or UDP pseudo-header.
+fn pseudo_header_checksum(addrs: &[u8], protocol: u8, payload_len: usize) -> u16 {
+    let mut sum = addrs
+        .chunks_exact(2)
+        .map(|word| u32::from(u16::from_be_bytes([word[0], word[1]])))
+        .sum::<u32>();
+    sum += u32::from(protocol) + payload_len as u32;
+
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    sum as u16
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    let bytes = bytes.get(offset..offset + size_of::<u16>())?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+const ETHERTYPE_OFFSET: usize = 12;
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+
+const IPV4_MIN_HEADER_LEN: usize = 20;
+const IPV4_MORE_FRAGMENTS: u16 = 0x2000;
+const IPV4_FRAGMENT_OFFSET: u16 = 0x1fff;
+const IPV6_HEADER_LEN: usize = 40;
+
+const IPPROTO_TCP: u8 = 6;
+const IPPROTO_UDP: u8 = 17;
+
+const TCP_CHECKSUM_OFFSET: usize = 16;
+const UDP_CHECKSUM_OFFSET: usize = 6;
@@ -9,7 +9,7 @@ use aster_util::slot_vec::SlotVec;
 use log::{debug, warn};
 use ostd::{arch::trap::TrapFrame, mm::VmReader, sync::SpinLock};
 
-use super::{config::VirtioNetConfig, header::VirtioNetHdr};
+use super::{checksum::prepare_partial_checksum, config::VirtioNetConfig, header::VirtioNetHdr};
 use crate::{
     device::{
         VirtioDeviceError,
@@ -27,11 +27,9 @@ pub struct NetworkDevice {
     // For smoltcp use
     caps: DeviceCapabilities,
     mac_addr: EthernetAddr,
+    features: NetworkFeatures,
     send_queue: VirtQueue,
     recv_queue: VirtQueue,
-    // Since the virtio net header remains consistent for each sending packet,
-    // we store it to avoid recreating the header repeatedly.
-    header: VirtioNetHdr,
     tx_buffers: Vec<Option<TxBuffer>>,
     rx_buffers: SlotVec<RxBuffer>,
     transport: Box<dyn VirtioTransport>,
@@ -109,9 +107,9 @@ impl NetworkDevice {
             config_manager,
             caps,
             mac_addr,
+            features,
             send_queue,
             recv_queue,
-            header: VirtioNetHdr::default(),
             tx_buffers,
             rx_buffers,
             transport,
@@ -190,14 +188,15 @@ impl NetworkDevice {
     }
 
     /// Sends a packet to network.
-    fn send(&mut self, packet: &[u8]) -> Result<(), NetError> {
+    fn send(&mut self, packet: &mut [u8]) -> Result<(), NetError> {
         if !self.can_send() {
             return Err(NetError::Busy);
         }
 
+        let header = self.new_send_header(packet);
         let tx_pool = TX_BUFFER_POOL.get().unwrap();
         let tx_buffer = TxBuffer::new(
-            &self.header,
+            &header,
             &mut VmReader::from(packet).to_fallible(),
             tx_pool,
         )
@@ -237,6 +236,21 @@ impl NetworkDevice {
         Ok(())
     }
 
+    /// Creates the virtio net header for a sending packet.
+    ///
+    /// If checksum offloading is enabled, the packet may be modified to let the device complete
+    /// its checksum.
+    fn new_send_header(&self, packet: &mut [u8]) -> VirtioNetHdr {
+        if !self.features.contains(NetworkFeatures::VIRTIO_NET_F_CSUM) {
+            return VirtioNetHdr::default();
+        }
+
+        match prepare_partial_checksum(packet) {
+            Some(csum) => VirtioNetHdr::new_partial_checksum(csum.start, csum.offset),
+            None => VirtioNetHdr::default(),
+        }
+    }
+
     fn notify_send_queue(&mut self) {
         if self.poll_stat.sent_packet == 0 {
             return;
@@ -302,16 +316,20 @@ fn init_caps(features: &NetworkFeatures, config: &VirtioNetConfig) -> DeviceCapa
         caps.max_transmission_unit = 1514;
     }
 
-    // We do not support checksum offloading.
-    // So the features must not be negotiated,
-    // and we must deliver fully checksummed packets to the device
-    // and validate all checksums for packets from the device.
-    assert!(
-        !features.contains(NetworkFeatures::VIRTIO_NET_F_CSUM)
-            && !features.contains(NetworkFeatures::VIRTIO_NET_F_GUEST_CSUM)
-    );
-    caps.checksum.tcp = Checksum::Both;
-    caps.checksum.udp = Checksum::Both;
+    // We do not support receive checksum offloading.
+    // So the feature must not be negotiated,
+    // and we must validate all checksums for packets from the device.
+    assert!(!features.contains(NetworkFeatures::VIRTIO_NET_F_GUEST_CSUM));
+
+    if features.contains(NetworkFeatures::VIRTIO_NET_F_CSUM) {
+        // The device completes the TCP and UDP checksums of the packets that we send.
+        // See `NetworkDevice::new_send_header` for details.
+        caps.checksum.tcp = Checksum::Rx;
+        caps.checksum.udp = Checksum::Rx;
+    } else {
+        caps.checksum.tcp = Checksum::Both;
+        caps.checksum.udp = Checksum::Both;
+    }
     caps.checksum.ipv4 = Checksum::Both;
     caps.checksum.icmpv4 = Checksum::Both;
 
@@ -339,7 +357,7 @@ impl AnyNetworkDevice for NetworkDevice {
         self.receive()
     }
 
-    fn send(&mut self, packet: &[u8]) -> Result<(), NetError> {
+    fn send(&mut self, packet: &mut [u8]) -> Result<(), NetError> {
         self.send(packet)
     }
 
@@ -19,6 +19,21 @@ pub(super) struct VirtioNetHdr {
                       // padding_reserved: u16,  // Only if VIRTIO_NET_F_HASH_REPORT negotiated
 }
 
+impl VirtioNetHdr {
+    /// Creates a header that asks the device to complete a partial checksum.
+    ///
+    /// The device computes the checksum from `csum_start` to the end of the packet and stores it
+    /// at `csum_start + csum_offset`.
+    pub(super) fn new_partial_checksum(csum_start: u16, csum_offset: u16) -> Self {
+        Self {
+            flags: Flags::VIRTIO_NET_HDR_F_NEEDS_CSUM,
+            csum_start,
+            csum_offset,
+            ..Self::default()
+        }
+    }
+}
+
 bitflags! {
     #[repr(C)]
     #[derive(Default, Pod)]
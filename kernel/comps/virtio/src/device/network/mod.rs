@@ -1,6 +1,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
 mod buffer;
+mod checksum;
 mod config;
 pub mod device;
 mod header;
@@ -0,0 +1,135 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The SCSI commands that are used to enumerate and access the disks.
+//!
+//! Reference: SCSI Block Commands - 3 (SBC-3) and SCSI Primary Commands - 4 (SPC-4).
+
+use alloc::vec::Vec;
+
+/// A SCSI command descriptor block.
+#[derive(Debug)]
+pub(super) struct Cdb {
+    bytes: [u8; 16],
+    len: usize,
+}
+
+impl Cdb {
+    fn new(opcode: u8, len: usize) -> Self {
+        let mut bytes = [0; 16];
+        bytes[0] = opcode;
+        Self { bytes, len }
+    }
+
+    pub(super) fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    /// Creates an INQUIRY command for the standard inquiry data.
+    pub(super) fn inquiry(alloc_len: u16) -> Self {
+        let mut cdb = Self::new(0x12, 6);
+        cdb.bytes[3..5].copy_from_slice(&alloc_len.to_be_bytes());
+        cdb
+    }
+
+    /// Creates a REPORT LUNS command.
+    pub(super) fn report_luns(alloc_len: u32) -> Self {
+        let mut cdb = Self::new(0xA0, 12);
+        cdb.bytes[6..10].copy_from_slice(&alloc_len.to_be_bytes());
+        cdb
+    }
+
+    /// Creates a READ CAPACITY (16) command.
+    pub(super) fn read_capacity_16(alloc_len: u32) -> Self {
+        const SERVICE_ACTION: u8 = 0x10;
+
+        let mut cdb = Self::new(0x9E, 16);
+        cdb.bytes[1] = SERVICE_ACTION;
+        cdb.bytes[10..14].copy_from_slice(&alloc_len.to_be_bytes());
+        cdb
+    }
+
+    /// Creates a READ (16) command.
+    pub(super) fn read_16(lba: u64, nr_blocks: u32) -> Self {
+        let mut cdb = Self::new(0x88, 16);
+        cdb.bytes[2..10].copy_from_slice(&lba.to_be_bytes());
+        cdb.bytes[10..14].copy_from_slice(&nr_blocks.to_be_bytes());
+        cdb
+    }
+
+    /// Creates a WRITE (16) command.
+    pub(super) fn write_16(lba: u64, nr_blocks: u32) -> Self {
+        let mut cdb = Self::read_16(lba, nr_blocks);
+        cdb.bytes[0] = 0x8A;
+        cdb
+    }
+
+    /// Creates a SYNCHRONIZE CACHE (10) command for the whole disk.
+    pub(super) fn synchronize_cache_10() -> Self {
+        Self::new(0x35, 10)
+    }
+
+    /// Creates an UNMAP command, whose parameter list is of `param_len` bytes.
+    pub(super) fn unmap(param_len: u16) -> Self {
+        let mut cdb = Self::new(0x42, 10);
+        cdb.bytes[7..9].copy_from_slice(&param_len.to_be_bytes());
+        cdb
+    }
+}
+
+/// The SCSI status that reports the success of a command.
+pub(super) const STATUS_GOOD: u8 = 0x00;
+/// The SCSI status that reports the sense data is available.
+pub(super) const STATUS_CHECK_CONDITION: u8 = 0x02;
+
+/// The sense key that reports a unit attention condition, e.g., after the disk is reset.
+pub(super) const SENSE_KEY_UNIT_ATTENTION: u8 = 0x6;
+
+/// Returns the sense key in the sense data.
+pub(super) fn sense_key(sense: &[u8]) -> Option<u8> {
+    match sense.first()? & 0x7F {
+        // Fixed format
+        0x70 | 0x71 => sense.get(2).map(|key| key & 0xF),
+        // Descriptor format
+        0x72 | 0x73 => sense.get(1).map(|key| key & 0xF),
+        _ => None,
+    }
+}
+
+/// The size of the UNMAP parameter list with one block descriptor.
+pub(super) const UNMAP_PARAM_SIZE: usize = 24;
+
+/// Returns the UNMAP parameter list with one block descriptor.
+pub(super) fn unmap_param(lba: u64, nr_blocks: u32) -> [u8; UNMAP_PARAM_SIZE] {
+    let mut param = [0; UNMAP_PARAM_SIZE];
+    // The UNMAP data length, excluding the field itself
+    param[0..2].copy_from_slice(&(UNMAP_PARAM_SIZE as u16 - 2).to_be_bytes());
+    // The UNMAP block descriptor data length
+    param[2..4].copy_from_slice(&16u16.to_be_bytes());
+    param[8..16].copy_from_slice(&lba.to_be_bytes());
+    param[16..20].copy_from_slice(&nr_blocks.to_be_bytes());
+    param
+}
+
+/// Returns the LUN numbers in the REPORT LUNS parameter data.
+///
+/// Only the LUNs that use the peripheral device or flat space addressing method are returned.
+pub(super) fn parse_report_luns(data: &[u8]) -> Vec<u16> {
+    let list_len = u32::from_be_bytes(data[0..4].try_into().unwrap()) as usize;
+    let end = data.len().min(8 + list_len);
+
+    data[8..end]
+        .chunks_exact(8)
+        .filter(|lun| lun[0] >> 6 <= 1 && lun[2..].iter().all(|byte| *byte == 0))
+        .map(|lun| u16::from_be_bytes([lun[0] & 0x3F, lun[1]]))
+        .collect()
+}
+
+/// Returns the LUN field of a request for the LUN of the target.
+pub(super) fn encode_lun(target: u16, lun: u16) -> [u8; 8] {
+    let mut bytes = [0; 8];
+    bytes[0] = 1;
+    bytes[1] = target as u8;
+    // The flat space addressing method
+    bytes[2..4].copy_from_slice(&(0x4000 | lun).to_be_bytes());
+    bytes
+}
@@ -0,0 +1,630 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{
+    boxed::Box,
+    collections::BTreeMap,
+    string::String,
+    sync::{Arc, Weak},
+    vec,
+    vec::Vec,
+};
+use core::{hint::spin_loop, iter};
+
+use aster_block::{
+    BlockDeviceMeta, EXTENDED_DEVICE_ID_ALLOCATOR, PartitionInfo, PartitionNode,
+    SCSI_DISK_ALLOCATOR, SECTOR_SIZE, ScsiDiskSlot,
+    bio::{BioEnqueueError, BioStatus, BioType, SubmittedBio, bio_segment_pool_init},
+    request_queue::{BioRequest, BioRequestQueue},
+};
+use aster_util::mem_obj_slice::Slice;
+use device_id::DeviceId;
+use log::{debug, info, warn};
+use ostd::{
+    arch::trap::TrapFrame,
+    mm::{PAGE_SIZE, VmIo, dma::DmaStream},
+    sync::SpinLock,
+};
+use ostd_pod::FromZeros;
+
+use super::{
+    REQ_SIZE, RESP_SIZE, Response, SENSE_SIZE, ScsiFeatures, ScsiReq, ScsiResp, VirtioScsiConfig,
+    command::{
+        Cdb, SENSE_KEY_UNIT_ATTENTION, STATUS_CHECK_CONDITION, STATUS_GOOD, UNMAP_PARAM_SIZE,
+        encode_lun, parse_report_luns, sense_key, unmap_param,
+    },
+};
+use crate::{
+    device::VirtioDeviceError,
+    id_alloc::SyncIdAlloc,
+    queue::VirtQueue,
+    transport::VirtioTransport,
+};
+
+/// The index of the first request queue. The control queue and the event queue are not used.
+const REQUEST_QUEUE_INDEX: u16 = 2;
+
+/// The maximum number of times that a command is retried on unit attention conditions.
+const MAX_RETRIES: usize = 3;
+
+/// A virtio SCSI host bus adapter.
+///
+/// The disks (i.e., the LUNs of the direct access block devices) attached to the adapter are
+/// enumerated and registered as [`ScsiDisk`]s.
+#[derive(Debug)]
+pub(crate) struct ScsiDevice {
+    queue: SpinLock<VirtQueue>,
+    transport: SpinLock<Box<dyn VirtioTransport>>,
+    requests: Arc<DmaStream>,
+    responses: Arc<DmaStream>,
+    unmap_params: Arc<DmaStream>,
+    id_allocator: SyncIdAlloc,
+    submitted_requests: SpinLock<BTreeMap<u16, SubmittedRequest>>,
+}
+
+/// The information of a disk.
+#[derive(Debug, Clone, Copy)]
+struct DiskInfo {
+    lun: [u8; 8],
+    nr_blocks: u64,
+    /// The size of a logical block, as a power of two.
+    lba_shift: u32,
+    supports_unmap: bool,
+}
+
+/// An error that occurs when executing a SCSI command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CommandError {
+    /// The target does not exist.
+    BadTarget,
+    /// The command fails with the sense key, if any.
+    CheckCondition(Option<u8>),
+    /// The command fails for other reasons.
+    Failed,
+}
+
+impl ScsiDevice {
+    const QUEUE_SIZE: u16 = 64;
+
+    /// Creates and inits the device, and registers the disks attached to it.
+    pub(crate) fn init(mut transport: Box<dyn VirtioTransport>) -> Result<(), VirtioDeviceError> {
+        let config_manager = VirtioScsiConfig::new_manager(transport.as_ref());
+        config_manager.write_sizes();
+        let config = config_manager.read_config();
+        debug!("virtio_scsi_config = {:?}", config);
+
+        let num_queues = transport.num_queues();
+        if num_queues <= REQUEST_QUEUE_INDEX {
+            return Err(VirtioDeviceError::QueuesAmountDoNotMatch(
+                num_queues,
+                REQUEST_QUEUE_INDEX + 1,
+            ));
+        }
+        // FIXME: Use all the request queues to submit the requests from multiple CPUs in parallel.
+        let queue = VirtQueue::new(REQUEST_QUEUE_INDEX, Self::QUEUE_SIZE, transport.as_mut())?;
+
+        let alloc_dma = |size: usize| {
+            DmaStream::alloc(size.div_ceil(PAGE_SIZE), false)
+                .map(Arc::new)
+                .map_err(|_| VirtioDeviceError::ResourceAllocError)
+        };
+        let requests = alloc_dma(Self::QUEUE_SIZE as usize * REQ_SIZE)?;
+        let responses = alloc_dma(Self::QUEUE_SIZE as usize * RESP_SIZE)?;
+        let unmap_params = alloc_dma(Self::QUEUE_SIZE as usize * UNMAP_PARAM_SIZE)?;
+
+        let device = Arc::new(Self {
+            queue: SpinLock::new(queue),
+            transport: SpinLock::new(transport),
+            requests,
+            responses,
+            unmap_params,
+            id_allocator: SyncIdAlloc::with_capacity(Self::QUEUE_SIZE as usize),
+            submitted_requests: SpinLock::new(BTreeMap::new()),
+        });
+
+        let cloned_device = device.clone();
+        let handle_irq = move |_: &TrapFrame| {
+            cloned_device.handle_irq();
+        };
+
+        {
+            let mut transport = device.transport.lock();
+            transport
+                .register_queue_callback(REQUEST_QUEUE_INDEX, Box::new(handle_irq), false)
+                .unwrap();
+            transport.finish_init();
+        }
+
+        // Each request includes an additional request and response descriptor.
+        let max_nr_segments = (Self::QUEUE_SIZE as usize - 2).min(config.seg_max.max(1) as usize);
+        device.scan(config.max_target, config.max_lun, max_nr_segments)?;
+
+        bio_segment_pool_init();
+        Ok(())
+    }
+
+    /// Negotiate features for the device specified bits 0~23
+    pub(crate) fn negotiate_features(features: u64) -> u64 {
+        let mut support_features = ScsiFeatures::from_bits_truncate(features);
+        // Hot-plugging and the protection information are not supported.
+        support_features.remove(
+            ScsiFeatures::HOTPLUG | ScsiFeatures::CHANGE | ScsiFeatures::T10_PI,
+        );
+        support_features.bits
+    }
+
+    /// Enumerates the LUNs of all the targets and registers the disks.
+    fn scan(
+        self: &Arc<Self>,
+        max_target: u16,
+        max_lun: u32,
+        max_nr_segments: usize,
+    ) -> Result<(), VirtioDeviceError> {
+        let buf = DmaStream::alloc(1, false)
+            .map(Arc::new)
+            .map_err(|_| VirtioDeviceError::ResourceAllocError)?;
+
+        for target in 0..=max_target.min(u8::MAX as u16) {
+            let report_luns = Cdb::report_luns(PAGE_SIZE as u32);
+            let luns = match self.execute_sync(target, 0, &report_luns, &buf, PAGE_SIZE) {
+                Ok(()) => {
+                    let mut data = vec![0u8; PAGE_SIZE];
+                    buf.read_bytes(0, &mut data).unwrap();
+                    parse_report_luns(&data)
+                }
+                Err(CommandError::BadTarget) => continue,
+                // Fall back to the first LUN if REPORT LUNS is not supported.
+                Err(_) => vec![0],
+            };
+
+            for lun in luns.into_iter().filter(|lun| *lun as u32 <= max_lun) {
+                if let Some(info) = self.probe_disk(target, lun, &buf) {
+                    ScsiDisk::init(self.clone(), info, max_nr_segments);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the information of the LUN if it is a disk that is ready.
+    fn probe_disk(&self, target: u16, lun: u16, buf: &Arc<DmaStream>) -> Option<DiskInfo> {
+        const INQUIRY_LEN: usize = 36;
+        const READ_CAPACITY_LEN: usize = 32;
+
+        let inquiry = Cdb::inquiry(INQUIRY_LEN as u16);
+        self.execute_sync(target, lun, &inquiry, buf, INQUIRY_LEN).ok()?;
+        let mut inquiry_data = [0u8; INQUIRY_LEN];
+        buf.read_bytes(0, &mut inquiry_data).unwrap();
+        // The peripheral qualifier and the peripheral device type. Only the direct access block
+        // devices that are connected are supported.
+        if inquiry_data[0] != 0 {
+            return None;
+        }
+
+        // The disk reports a unit attention condition after it is reset.
+        let read_capacity = Cdb::read_capacity_16(READ_CAPACITY_LEN as u32);
+        let mut nr_retries = 0;
+        loop {
+            match self.execute_sync(target, lun, &read_capacity, buf, READ_CAPACITY_LEN) {
+                Ok(()) => break,
+                Err(CommandError::CheckCondition(Some(SENSE_KEY_UNIT_ATTENTION)))
+                    if nr_retries < MAX_RETRIES =>
+                {
+                    nr_retries += 1;
+                }
+                Err(err) => {
+                    warn!(
+                        "failed to read the capacity of SCSI disk {}:{}: {:?}",
+                        target, lun, err
+                    );
+                    return None;
+                }
+            }
+        }
+        let mut capacity_data = [0u8; READ_CAPACITY_LEN];
+        buf.read_bytes(0, &mut capacity_data).unwrap();
+
+        let last_lba = u64::from_be_bytes(capacity_data[0..8].try_into().unwrap());
+        let block_size = u32::from_be_bytes(capacity_data[8..12].try_into().unwrap()) as usize;
+        // The LBPME bit, which indicates that UNMAP is supported.
+        let supports_unmap = capacity_data[14] & 0x80 != 0;
+        if !block_size.is_power_of_two() || !(SECTOR_SIZE..=PAGE_SIZE).contains(&block_size) {
+            warn!(
+                "SCSI disk {}:{} has an unsupported block size {}",
+                target, lun, block_size
+            );
+            return None;
+        }
+
+        info!(
+            "SCSI disk {}:{}: {} {}",
+            target,
+            lun,
+            String::from_utf8_lossy(&inquiry_data[8..16]).trim_end(),
+            String::from_utf8_lossy(&inquiry_data[16..32]).trim_end()
+        );
+
+        Some(DiskInfo {
+            lun: encode_lun(target, lun),
+            nr_blocks: last_lba + 1,
+            lba_shift: block_size.ilog2(),
+            supports_unmap,
+        })
+    }
+
+    /// Executes a command whose data is read into `buf`, and waits for its completion.
+    ///
+    /// The completion is polled, so this method can be used before the interrupts are enabled.
+    fn execute_sync(
+        &self,
+        target: u16,
+        lun: u16,
+        cdb: &Cdb,
+        buf: &Arc<DmaStream>,
+        len: usize,
+    ) -> Result<(), CommandError> {
+        let result = Arc::new(SpinLock::new(None));
+        let data_in = Slice::new(buf.clone(), 0..len);
+        self.submit(
+            &encode_lun(target, lun),
+            cdb,
+            Pending::Sync(data_in, result.clone()),
+        );
+
+        let resp = loop {
+            self.handle_irq();
+            if let Some(resp) = result.disable_irq().lock().take() {
+                break resp;
+            }
+            spin_loop();
+        };
+        check_response(&resp)
+    }
+
+    /// Submits a read, write, or flush request to the disk.
+    fn submit_bio(&self, info: &DiskInfo, request: BioRequest) {
+        let cdb = if request.type_() == BioType::Flush {
+            Cdb::synchronize_cache_10()
+        } else {
+            let Some((lba, nr_blocks)) = lba_range(info, &request) else {
+                warn!("unaligned SCSI request: {:?}", request);
+                complete_request(&request, BioStatus::IoError);
+                return;
+            };
+            match request.type_() {
+                BioType::Read => Cdb::read_16(lba, nr_blocks),
+                BioType::Write => Cdb::write_16(lba, nr_blocks),
+                _ => unreachable!(),
+            }
+        };
+
+        self.submit(&info.lun, &cdb, Pending::Bio(request));
+    }
+
+    /// Submits a discard request to the disk.
+    fn submit_unmap(&self, info: &DiskInfo, request: BioRequest) {
+        let Some((lba, nr_blocks)) = lba_range(info, &request) else {
+            warn!("unaligned SCSI request: {:?}", request);
+            complete_request(&request, BioStatus::IoError);
+            return;
+        };
+
+        let cdb = Cdb::unmap(UNMAP_PARAM_SIZE as u16);
+        let param = unmap_param(lba, nr_blocks);
+        self.submit(&info.lun, &cdb, Pending::Unmap(request, param));
+    }
+
+    /// Submits a command, this function is non-blocking.
+    fn submit(&self, lun: &[u8; 8], cdb: &Cdb, pending: Pending) {
+        let id = self.id_allocator.alloc();
+        let req_slice = {
+            let req_slice = Slice::new(self.requests.clone(), id * REQ_SIZE..(id + 1) * REQ_SIZE);
+            let mut req = ScsiReq::new_zeroed();
+            req.lun = *lun;
+            req.tag = (id as u64).to_le_bytes();
+            req.cdb[..cdb.as_bytes().len()].copy_from_slice(cdb.as_bytes());
+            req_slice.write_val(0, &req).unwrap();
+            req_slice.sync_to_device().unwrap();
+            req_slice
+        };
+
+        let resp_slice = {
+            let resp_slice =
+                Slice::new(self.responses.clone(), id * RESP_SIZE..(id + 1) * RESP_SIZE);
+            resp_slice.write_val(0, &ScsiResp::new_zeroed()).unwrap();
+            resp_slice.sync_to_device().unwrap();
+            resp_slice
+        };
+
+        let unmap_param_slice;
+        let (data_out, data_in): (Vec<&Slice<_>>, Vec<&Slice<_>>) = match &pending {
+            Pending::Bio(request) => {
+                let dma_slices = request.bios().flat_map(|bio| {
+                    bio.segments()
+                        .iter()
+                        .map(|segment| segment.inner_dma_slice())
+                });
+                match request.type_() {
+                    BioType::Read => (Vec::new(), dma_slices.collect()),
+                    BioType::Write => (dma_slices.collect(), Vec::new()),
+                    _ => (Vec::new(), Vec::new()),
+                }
+            }
+            Pending::Unmap(_, param) => {
+                unmap_param_slice = Slice::new(
+                    self.unmap_params.clone(),
+                    id * UNMAP_PARAM_SIZE..(id + 1) * UNMAP_PARAM_SIZE,
+                );
+                unmap_param_slice.write_bytes(0, param).unwrap();
+                unmap_param_slice.sync_to_device().unwrap();
+                (vec![&unmap_param_slice], Vec::new())
+            }
+            Pending::Sync(data_in, _) => (Vec::new(), vec![data_in]),
+        };
+
+        let inputs: Vec<&Slice<_>> = iter::once(&req_slice).chain(data_out).collect();
+        let outputs: Vec<&Slice<_>> = iter::once(&resp_slice).chain(data_in).collect();
+
+        let num_used_descs = inputs.len() + outputs.len();
+        // The request queue limits the number of segments in a request.
+        assert!(num_used_descs <= Self::QUEUE_SIZE as usize);
+
+        loop {
+            let mut queue = self.queue.disable_irq().lock();
+            if num_used_descs > queue.available_desc() {
+                continue;
+            }
+            let token = queue
+                .add_dma_buf(inputs.as_slice(), outputs.as_slice())
+                .expect("add queue failed");
+            if queue.should_notify() {
+                queue.notify();
+            }
+
+            // Records the submitted request
+            let submitted_request = SubmittedRequest {
+                id: id as u16,
+                pending,
+            };
+            self.submitted_requests
+                .disable_irq()
+                .lock()
+                .insert(token, submitted_request);
+            return;
+        }
+    }
+
+    /// Handles the irq issued from the device
+    fn handle_irq(&self) {
+        loop {
+            // Pops the complete request
+            let complete_request = {
+                let mut queue = self.queue.disable_irq().lock();
+                let Ok((token, _)) = queue.pop_used() else {
+                    return;
+                };
+                self.submitted_requests
+                    .disable_irq()
+                    .lock()
+                    .remove(&token)
+                    .unwrap()
+            };
+
+            // Handles the response
+            let id = complete_request.id as usize;
+            let resp_slice = Slice::new(&self.responses, id * RESP_SIZE..(id + 1) * RESP_SIZE);
+            resp_slice.sync_from_device().unwrap();
+            let resp: ScsiResp = resp_slice.read_val(0).unwrap();
+            self.id_allocator.dealloc(id);
+
+            let bio_request = match complete_request.pending {
+                Pending::Bio(bio_request) | Pending::Unmap(bio_request, _) => bio_request,
+                Pending::Sync(data_in, result) => {
+                    data_in.sync_from_device().unwrap();
+                    *result.disable_irq().lock() = Some(resp);
+                    continue;
+                }
+            };
+
+            let status = match check_response(&resp) {
+                Ok(()) => BioStatus::Complete,
+                Err(err) => {
+                    warn!("SCSI request failed: {:?}", err);
+                    BioStatus::IoError
+                }
+            };
+
+            // Synchronize DMA mapping if read from the device
+            if status == BioStatus::Complete && bio_request.type_() == BioType::Read {
+                bio_request
+                    .bios()
+                    .flat_map(|bio| {
+                        bio.segments()
+                            .iter()
+                            .map(|segment| segment.inner_dma_slice())
+                    })
+                    .for_each(|dma_slice| dma_slice.sync_from_device().unwrap());
+            }
+
+            complete_request(&bio_request, status);
+        }
+    }
+}
+
+/// Returns the starting LBA and the number of logical blocks of the request.
+///
+/// This method returns `None` if the request is not aligned to the logical blocks.
+fn lba_range(info: &DiskInfo, request: &BioRequest) -> Option<(u64, u32)> {
+    let sector_shift = info.lba_shift - SECTOR_SIZE.ilog2();
+    let start = request.sid_range().start.to_raw();
+    let nr_sectors = request.num_sectors() as u64;
+    if (start | nr_sectors) & ((1 << sector_shift) - 1) != 0 {
+        return None;
+    }
+
+    Some((start >> sector_shift, (nr_sectors >> sector_shift) as u32))
+}
+
+fn check_response(resp: &ScsiResp) -> Result<(), CommandError> {
+    match Response::try_from(resp.response) {
+        Ok(Response::Ok) => {}
+        Ok(Response::BadTarget) => return Err(CommandError::BadTarget),
+        _ => return Err(CommandError::Failed),
+    }
+
+    match resp.status {
+        STATUS_GOOD => Ok(()),
+        STATUS_CHECK_CONDITION => {
+            let sense_len = (resp.sense_len as usize).min(SENSE_SIZE);
+            Err(CommandError::CheckCondition(sense_key(&resp.sense[..sense_len])))
+        }
+        _ => Err(CommandError::Failed),
+    }
+}
+
+fn complete_request(request: &BioRequest, status: BioStatus) {
+    request.bios().for_each(|bio| bio.complete(status));
+}
+
+/// A submitted command for callback.
+#[derive(Debug)]
+struct SubmittedRequest {
+    id: u16,
+    pending: Pending,
+}
+
+/// The purpose of a submitted command.
+#[derive(Debug)]
+enum Pending {
+    /// A read, write, or flush request.
+    Bio(BioRequest),
+    /// A discard request with its UNMAP parameter list.
+    Unmap(BioRequest, [u8; UNMAP_PARAM_SIZE]),
+    /// A command executed by [`ScsiDevice::execute_sync`] with its data-in buffer and the slot
+    /// for its response.
+    Sync(Slice<Arc<DmaStream>>, Arc<SpinLock<Option<ScsiResp>>>),
+}
+
+/// A disk attached to a virtio SCSI host bus adapter.
+#[derive(Debug)]
+pub struct ScsiDisk {
+    device: Arc<ScsiDevice>,
+    info: DiskInfo,
+    /// The software staging queue.
+    queue: BioRequestQueue,
+    slot: ScsiDiskSlot,
+    partitions: SpinLock<Option<Vec<Arc<PartitionNode>>>>,
+    weak_self: Weak<Self>,
+}
+
+impl ScsiDisk {
+    /// Creates a disk and registers it.
+    fn init(device: Arc<ScsiDevice>, info: DiskInfo, max_nr_segments: usize) {
+        let Ok(slot) = SCSI_DISK_ALLOCATOR.get().unwrap().allocate() else {
+            warn!("too many SCSI disks");
+            return;
+        };
+
+        let disk = Arc::new_cyclic(|weak_self| ScsiDisk {
+            device,
+            info,
+            queue: BioRequestQueue::with_max_nr_segments_per_bio(max_nr_segments),
+            slot,
+            partitions: SpinLock::new(None),
+            weak_self: weak_self.clone(),
+        });
+
+        aster_block::register(disk).unwrap();
+    }
+
+    /// Dequeues a `BioRequest` from the software staging queue and
+    /// processes the request.
+    pub fn handle_requests(&self) {
+        let request = self.queue.dequeue();
+        debug!("Handle Request: {:?}", request);
+        match request.type_() {
+            BioType::Read | BioType::Write | BioType::Flush => {
+                self.device.submit_bio(&self.info, request)
+            }
+            BioType::Discard if self.info.supports_unmap => {
+                self.device.submit_unmap(&self.info, request)
+            }
+            BioType::Discard => complete_request(&request, BioStatus::NotSupported),
+        }
+    }
+}
+
+impl aster_block::BlockDevice for ScsiDisk {
+    fn enqueue(&self, bio: SubmittedBio) -> Result<(), BioEnqueueError> {
+        self.queue.enqueue(bio)
+    }
+
+    fn metadata(&self) -> BlockDeviceMeta {
+        BlockDeviceMeta {
+            max_nr_segments_per_bio: self.queue.max_nr_segments_per_bio(),
+            nr_sectors: (self.info.nr_blocks << self.info.lba_shift) as usize / SECTOR_SIZE,
+            supports_fua: false,
+            max_discard_sectors: if self.info.supports_unmap {
+                // An UNMAP block descriptor has at most `u32::MAX` logical blocks.
+                (u32::MAX as usize) << (self.info.lba_shift - SECTOR_SIZE.ilog2())
+            } else {
+                0
+            },
+        }
+    }
+
+    fn name(&self) -> &str {
+        self.slot.name()
+    }
+
+    fn id(&self) -> DeviceId {
+        self.slot.id()
+    }
+
+    fn set_partitions(&self, infos: Vec<Option<PartitionInfo>>) {
+        let old_partitions = self.partitions.lock().take();
+        for partition in old_partitions.into_iter().flatten() {
+            let _ = aster_block::unregister(partition.id());
+            // This does nothing if the ID is not an extended device ID.
+            EXTENDED_DEVICE_ID_ALLOCATOR
+                .get()
+                .unwrap()
+                .release(partition.id());
+        }
+
+        let mut new_partitions = Vec::new();
+        for (index, info_opt) in infos.iter().enumerate() {
+            let Some(info) = info_opt else {
+                continue;
+            };
+
+            let number = index as u32 + 1;
+            let id = self.slot.allocate_partition_id(number);
+            let name = aster_block::partition_name(self.name(), number);
+            let device = self.weak_self.upgrade().unwrap();
+
+            let partition = Arc::new(PartitionNode::new(id, name, device, number, *info));
+            new_partitions.push(partition);
+        }
+
+        for partition in new_partitions.iter() {
+            let _ = aster_block::register(partition.clone());
+        }
+
+        *self.partitions.lock() = Some(new_partitions);
+    }
+
+    fn partitions(&self) -> Option<Vec<Arc<dyn aster_block::BlockDevice>>> {
+        let partitions = self.partitions.lock();
+        let devices = partitions
+            .as_ref()?
+            .iter()
+            .map(|p| p.clone() as Arc<dyn aster_block::BlockDevice>)
+            .collect();
+        Some(devices)
+    }
+
+    fn request_queue(&self) -> Option<&BioRequestQueue> {
+        Some(&self.queue)
+    }
+}
@@ -0,0 +1,160 @@
+// SPDX-License-Identifier: MPL-2.0
+
+mod command;
+pub mod device;
+
+use core::mem::offset_of;
+
+use aster_util::safe_ptr::SafePtr;
+use bitflags::bitflags;
+use int_to_c_enum::TryFromInt;
+use ostd_pod::FromZeros;
+
+use crate::transport::{ConfigManager, VirtioTransport};
+
+pub const DEVICE_NAME: &str = "Virtio-SCSI";
+
+bitflags! {
+    /// features for virtio SCSI device
+    struct ScsiFeatures : u64 {
+        const INOUT     = 1 << 0;
+        const HOTPLUG   = 1 << 1;
+        const CHANGE    = 1 << 2;
+        const T10_PI    = 1 << 3;
+    }
+}
+
+/// The size of the CDB in a request, which is the default value of the device.
+const CDB_SIZE: usize = 32;
+/// The size of the sense data in a response, which is the default value of the device.
+const SENSE_SIZE: usize = 96;
+
+/// The response codes of the requests.
+#[repr(u8)]
+#[derive(Debug, Eq, PartialEq, Copy, Clone, TryFromInt)]
+enum Response {
+    Ok = 0,
+    Overrun = 1,
+    Aborted = 2,
+    BadTarget = 3,
+    Reset = 4,
+    Busy = 5,
+    TransportFailure = 6,
+    TargetFailure = 7,
+    NexusFailure = 8,
+    Failure = 9,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod)]
+struct VirtioScsiConfig {
+    /// The number of request queues.
+    num_queues: u32,
+    /// The maximum number of segments in a command.
+    seg_max: u32,
+    /// The maximum number of sectors in a command.
+    max_sectors: u32,
+    /// The maximum number of linked commands per LUN.
+    cmd_per_lun: u32,
+    /// The size of the events in the event queue.
+    event_info_size: u32,
+    /// The size of the sense data, which is writable by the driver.
+    sense_size: u32,
+    /// The size of the CDB, which is writable by the driver.
+    cdb_size: u32,
+    max_channel: u16,
+    max_target: u16,
+    max_lun: u32,
+}
+
+/// The device-readable header of a request.
+///
+/// All the fields are byte arrays, so the header has no padding and is followed by the data-out
+/// buffers immediately.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod)]
+struct ScsiReq {
+    lun: [u8; 8],
+    /// The command tag, in little endian.
+    tag: [u8; 8],
+    task_attr: u8,
+    prio: u8,
+    crn: u8,
+    cdb: [u8; CDB_SIZE],
+}
+
+const REQ_SIZE: usize = size_of::<ScsiReq>();
+
+/// The device-writable header of a response, which precedes the data-in buffers.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod)]
+struct ScsiResp {
+    sense_len: u32,
+    /// The residual number of bytes that are not transferred.
+    resid: u32,
+    status_qualifier: u16,
+    /// The SCSI status of the command.
+    status: u8,
+    response: u8,
+    sense: [u8; SENSE_SIZE],
+}
+
+const RESP_SIZE: usize = size_of::<ScsiResp>();
+
+impl VirtioScsiConfig {
+    pub(self) fn new_manager(transport: &dyn VirtioTransport) -> ConfigManager<Self> {
+        let safe_ptr = transport
+            .device_config_mem()
+            .map(|mem| SafePtr::new(mem, 0));
+        let bar_space = transport.device_config_bar();
+
+        ConfigManager::new(safe_ptr, bar_space)
+    }
+}
+
+impl ConfigManager<VirtioScsiConfig> {
+    pub(self) fn read_config(&self) -> VirtioScsiConfig {
+        let mut scsi_config = VirtioScsiConfig::new_zeroed();
+
+        scsi_config.num_queues = self
+            .read_once::<u32>(offset_of!(VirtioScsiConfig, num_queues))
+            .unwrap();
+        scsi_config.seg_max = self
+            .read_once::<u32>(offset_of!(VirtioScsiConfig, seg_max))
+            .unwrap();
+        scsi_config.max_sectors = self
+            .read_once::<u32>(offset_of!(VirtioScsiConfig, max_sectors))
+            .unwrap();
+        scsi_config.cmd_per_lun = self
+            .read_once::<u32>(offset_of!(VirtioScsiConfig, cmd_per_lun))
+            .unwrap();
+        scsi_config.event_info_size = self
+            .read_once::<u32>(offset_of!(VirtioScsiConfig, event_info_size))
+            .unwrap();
+        scsi_config.sense_size = self
+            .read_once::<u32>(offset_of!(VirtioScsiConfig, sense_size))
+            .unwrap();
+        scsi_config.cdb_size = self
+            .read_once::<u32>(offset_of!(VirtioScsiConfig, cdb_size))
+            .unwrap();
+        scsi_config.max_channel = self
+            .read_once::<u16>(offset_of!(VirtioScsiConfig, max_channel))
+            .unwrap();
+        scsi_config.max_target = self
+            .read_once::<u16>(offset_of!(VirtioScsiConfig, max_target))
+            .unwrap();
+        scsi_config.max_lun = self
+            .read_once::<u32>(offset_of!(VirtioScsiConfig, max_lun))
+            .unwrap();
+
+        scsi_config
+    }
+
+    /// Sets the sizes of the CDBs and the sense data to the ones used by the driver.
+    pub(self) fn write_sizes(&self) {
+        self.write_once(offset_of!(VirtioScsiConfig, cdb_size), CDB_SIZE as u32)
+            .unwrap();
+        self.write_once(offset_of!(VirtioScsiConfig, sense_size), SENSE_SIZE as u32)
+            .unwrap();
+    }
+}
@@ -0,0 +1,47 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::mem::offset_of;
+
+use aster_util::safe_ptr::SafePtr;
+use ostd_pod::FromZeros;
+
+use crate::transport::{ConfigManager, VirtioTransport};
+
+#[derive(Debug, Pod, Clone, Copy)]
+#[repr(C)]
+pub(super) struct VirtioSoundConfig {
+    /// The number of the available jacks
+    pub jacks: u32,
+    /// The number of the available PCM streams
+    pub streams: u32,
+    /// The number of the available channel maps
+    pub chmaps: u32,
+}
+
+impl VirtioSoundConfig {
+    pub(super) fn new_manager(transport: &dyn VirtioTransport) -> ConfigManager<Self> {
+        let safe_ptr = transport
+            .device_config_mem()
+            .map(|mem| SafePtr::new(mem, 0));
+        let bar_space = transport.device_config_bar();
+        ConfigManager::new(safe_ptr, bar_space)
+    }
+}
+
+impl ConfigManager<VirtioSoundConfig> {
+    pub(super) fn read_config(&self) -> VirtioSoundConfig {
+        let mut sound_config = VirtioSoundConfig::new_zeroed();
+
+        sound_config.jacks = self
+            .read_once::<u32>(offset_of!(VirtioSoundConfig, jacks))
+            .unwrap();
+        sound_config.streams = self
+            .read_once::<u32>(offset_of!(VirtioSoundConfig, streams))
+            .unwrap();
+        sound_config.chmaps = self
+            .read_once::<u32>(offset_of!(VirtioSoundConfig, chmaps))
+            .unwrap();
+
+        sound_config
+    }
+}
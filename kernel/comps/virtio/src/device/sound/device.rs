@@ -0,0 +1,455 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{boxed::Box, collections::BTreeMap, sync::Arc, vec, vec::Vec};
+use core::{fmt::Debug, ops::Range};
+
+use aster_util::mem_obj_slice::Slice;
+use log::debug;
+use ostd::{
+    arch::trap::TrapFrame,
+    mm::{PAGE_SIZE, dma::DmaStream, io::util::HasVmReaderWriter},
+    sync::{Mutex, SpinLock, WaitQueue},
+};
+use ostd_pod::Pod;
+
+use super::{
+    PcmDirection, PcmInfo, PcmParams, SoundError,
+    config::VirtioSoundConfig,
+    protocol::{
+        Header, PcmHeader, PcmInfoRaw, PcmSetParams, PcmStatus, PcmXfer, QueryInfo, RequestCode,
+        Status,
+    },
+    register_device,
+};
+use crate::{
+    device::VirtioDeviceError,
+    queue::VirtQueue,
+    transport::{ConfigManager, VirtioTransport},
+};
+
+/// The size of the buffers that hold a request and its response.
+const COMMAND_BUFFER_SIZE: usize = PAGE_SIZE;
+
+/// The number of descriptors in the TX queue and the RX queue.
+const XFER_QUEUE_SIZE: u16 = 64;
+
+/// The callback that is invoked when the device completes an I/O message.
+///
+/// The result contains the number of the captured bytes for the capture streams, or zero for the
+/// playback streams. The callback is invoked in the interrupt context.
+pub type XferCallback = dyn Fn(&Arc<PcmBuffer>, Result<usize, SoundError>) + Send + Sync;
+
+/// The virtio sound device.
+///
+/// The control requests are sent one at a time, and the caller sleeps until the device responds.
+/// The I/O messages are completed asynchronously, and the completions are reported to the
+/// callbacks of the streams.
+pub struct SoundDevice {
+    config_manager: ConfigManager<VirtioSoundConfig>,
+    transport: SpinLock<Box<dyn VirtioTransport>>,
+    control_queue: SpinLock<VirtQueue>,
+    request_buffer: DmaStream,
+    response_buffer: DmaStream,
+    /// The lock that serializes the requests, because there is only one pair of buffers.
+    command_lock: Mutex<()>,
+    wait_queue: WaitQueue,
+    tx_queue: SpinLock<XferQueue>,
+    rx_queue: SpinLock<XferQueue>,
+    xfer_callbacks: SpinLock<BTreeMap<u32, Arc<XferCallback>>>,
+}
+
+impl Debug for SoundDevice {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SoundDevice")
+            .field("config", &self.config_manager.read_config())
+            .field("transport", &self.transport)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A TX queue or an RX queue with the buffers that are owned by the device.
+struct XferQueue {
+    queue: VirtQueue,
+    /// The pending buffers, indexed by the tokens of the queue.
+    pending: Vec<Option<Arc<PcmBuffer>>>,
+}
+
+impl XferQueue {
+    fn new(queue: VirtQueue) -> Self {
+        let pending = vec![None; queue.size() as usize];
+        Self { queue, pending }
+    }
+}
+
+impl SoundDevice {
+    pub(crate) fn negotiate_features(_features: u64) -> u64 {
+        // The control elements are not supported yet.
+        0
+    }
+
+    pub(crate) fn init(mut transport: Box<dyn VirtioTransport>) -> Result<(), VirtioDeviceError> {
+        // The event queue (queue 1) is not used, since neither the jacks nor the notifications of
+        // the PCM streams are supported yet.
+        const CONTROL_QUEUE_INDEX: u16 = 0;
+        const TX_QUEUE_INDEX: u16 = 2;
+        const RX_QUEUE_INDEX: u16 = 3;
+
+        let config_manager = VirtioSoundConfig::new_manager(transport.as_ref());
+        debug!("virtio_sound_config = {:?}", config_manager.read_config());
+
+        let control_queue = VirtQueue::new(CONTROL_QUEUE_INDEX, 2, transport.as_mut())?;
+        let tx_queue = VirtQueue::new(TX_QUEUE_INDEX, XFER_QUEUE_SIZE, transport.as_mut())?;
+        let rx_queue = VirtQueue::new(RX_QUEUE_INDEX, XFER_QUEUE_SIZE, transport.as_mut())?;
+
+        let request_buffer = DmaStream::alloc(COMMAND_BUFFER_SIZE / PAGE_SIZE, false)
+            .map_err(|_| VirtioDeviceError::ResourceAllocError)?;
+        let response_buffer = DmaStream::alloc(COMMAND_BUFFER_SIZE / PAGE_SIZE, false)
+            .map_err(|_| VirtioDeviceError::ResourceAllocError)?;
+
+        let device = Arc::new(Self {
+            config_manager,
+            transport: SpinLock::new(transport),
+            control_queue: SpinLock::new(control_queue),
+            request_buffer,
+            response_buffer,
+            command_lock: Mutex::new(()),
+            wait_queue: WaitQueue::new(),
+            tx_queue: SpinLock::new(XferQueue::new(tx_queue)),
+            rx_queue: SpinLock::new(XferQueue::new(rx_queue)),
+            xfer_callbacks: SpinLock::new(BTreeMap::new()),
+        });
+
+        // Register irq callbacks
+        let mut transport = device.transport.disable_irq().lock();
+        let handle_response = {
+            let device = device.clone();
+            move |_: &TrapFrame| device.wait_queue.wake_all()
+        };
+        transport
+            .register_queue_callback(CONTROL_QUEUE_INDEX, Box::new(handle_response), false)
+            .unwrap();
+        let handle_tx = {
+            let device = device.clone();
+            move |_: &TrapFrame| device.handle_xfer_completion(&device.tx_queue)
+        };
+        transport
+            .register_queue_callback(TX_QUEUE_INDEX, Box::new(handle_tx), false)
+            .unwrap();
+        let handle_rx = {
+            let device = device.clone();
+            move |_: &TrapFrame| device.handle_xfer_completion(&device.rx_queue)
+        };
+        transport
+            .register_queue_callback(RX_QUEUE_INDEX, Box::new(handle_rx), false)
+            .unwrap();
+        transport.finish_init();
+        drop(transport);
+
+        register_device(device);
+
+        Ok(())
+    }
+
+    /// Returns the information of the PCM streams.
+    pub fn query_pcm_infos(&self) -> Result<Vec<PcmInfo>, SoundError> {
+        const INFO_SIZE: usize = size_of::<PcmInfoRaw>();
+        const MAX_NR_STREAMS: usize = (COMMAND_BUFFER_SIZE - size_of::<Header>()) / INFO_SIZE;
+
+        let nr_streams = (self.config_manager.read_config().streams as usize).min(MAX_NR_STREAMS);
+        if nr_streams == 0 {
+            return Ok(Vec::new());
+        }
+
+        let request = QueryInfo {
+            header: Header::new(RequestCode::PcmInfo),
+            start_id: 0,
+            count: nr_streams as u32,
+            size: INFO_SIZE as u32,
+        };
+        let mut response = vec![0u8; nr_streams * INFO_SIZE];
+        self.send_command(&request, &mut response)?;
+
+        let infos = response
+            .chunks_exact(INFO_SIZE)
+            .enumerate()
+            .filter_map(|(stream_id, bytes)| {
+                let raw = PcmInfoRaw::from_bytes(bytes);
+                Some(PcmInfo {
+                    stream_id: stream_id as u32,
+                    nid: raw.hda_fn_nid,
+                    direction: PcmDirection::try_from(raw.direction).ok()?,
+                    formats: raw.formats,
+                    rates: raw.rates,
+                    channels_min: raw.channels_min,
+                    channels_max: raw.channels_max,
+                })
+            })
+            .collect();
+        Ok(infos)
+    }
+
+    /// Sets the parameters of a PCM stream.
+    pub fn set_params(&self, stream_id: u32, params: &PcmParams) -> Result<(), SoundError> {
+        let request = PcmSetParams {
+            pcm_header: PcmHeader::new(RequestCode::PcmSetParams, stream_id),
+            buffer_bytes: params.buffer_bytes,
+            period_bytes: params.period_bytes,
+            features: 0,
+            channels: params.channels,
+            format: params.format as u8,
+            rate: params.rate as u8,
+            padding: 0,
+        };
+        self.send_command(&request, &mut [])
+    }
+
+    /// Prepares a PCM stream, which allocates the resources on the host.
+    pub fn prepare(&self, stream_id: u32) -> Result<(), SoundError> {
+        self.send_pcm_command(RequestCode::PcmPrepare, stream_id)
+    }
+
+    /// Releases a PCM stream, which frees the resources on the host.
+    ///
+    /// The device completes all the pending I/O messages of the stream before it responds.
+    pub fn release(&self, stream_id: u32) -> Result<(), SoundError> {
+        self.send_pcm_command(RequestCode::PcmRelease, stream_id)
+    }
+
+    /// Starts a PCM stream.
+    pub fn start(&self, stream_id: u32) -> Result<(), SoundError> {
+        self.send_pcm_command(RequestCode::PcmStart, stream_id)
+    }
+
+    /// Stops a PCM stream.
+    pub fn stop(&self, stream_id: u32) -> Result<(), SoundError> {
+        self.send_pcm_command(RequestCode::PcmStop, stream_id)
+    }
+
+    /// Sets the callback that is invoked when an I/O message of a PCM stream is completed.
+    pub fn set_xfer_callback(&self, stream_id: u32, callback: Box<XferCallback>) {
+        self.xfer_callbacks
+            .disable_irq()
+            .lock()
+            .insert(stream_id, Arc::from(callback));
+    }
+
+    /// Submits the first `len` bytes of a buffer to be played.
+    pub fn submit_playback(&self, buffer: Arc<PcmBuffer>, len: usize) -> Result<(), SoundError> {
+        assert!(len <= buffer.capacity);
+
+        let inputs = buffer.header_range().start..buffer.data_range(len).end;
+        let outputs = buffer.status_range();
+        Self::submit(&self.tx_queue, buffer, inputs, outputs)
+    }
+
+    /// Submits a buffer to be filled with the captured frames.
+    pub fn submit_capture(&self, buffer: Arc<PcmBuffer>) -> Result<(), SoundError> {
+        let inputs = buffer.header_range();
+        let outputs = buffer.data_range(buffer.capacity).start..buffer.status_range().end;
+        Self::submit(&self.rx_queue, buffer, inputs, outputs)
+    }
+
+    fn submit(
+        queue: &SpinLock<XferQueue>,
+        buffer: Arc<PcmBuffer>,
+        inputs: Range<usize>,
+        outputs: Range<usize>,
+    ) -> Result<(), SoundError> {
+        let input_slice = Slice::new(&buffer.dma_stream, inputs);
+        let output_slice = Slice::new(&buffer.dma_stream, outputs);
+
+        let mut queue = queue.disable_irq().lock();
+        let token = queue
+            .queue
+            .add_dma_buf(&[&input_slice], &[&output_slice])
+            .map_err(|_| SoundError::QueueFull)?;
+        queue.pending[token as usize] = Some(buffer.clone());
+        if queue.queue.should_notify() {
+            queue.queue.notify();
+        }
+
+        Ok(())
+    }
+
+    fn handle_xfer_completion(&self, queue: &SpinLock<XferQueue>) {
+        loop {
+            let mut locked_queue = queue.disable_irq().lock();
+            let Ok((token, used_len)) = locked_queue.queue.pop_used() else {
+                break;
+            };
+            let buffer = locked_queue.pending[token as usize].take().unwrap();
+            // The callback may submit the buffer again, so the lock must be released first.
+            drop(locked_queue);
+
+            let result = buffer.complete(used_len as usize);
+            let callback = self
+                .xfer_callbacks
+                .disable_irq()
+                .lock()
+                .get(&buffer.stream_id)
+                .cloned();
+            if let Some(callback) = callback {
+                callback(&buffer, result);
+            }
+        }
+    }
+
+    fn send_pcm_command(&self, code: RequestCode, stream_id: u32) -> Result<(), SoundError> {
+        let request = PcmHeader::new(code, stream_id);
+        self.send_command(&request, &mut [])
+    }
+
+    /// Sends a request and waits for the response.
+    ///
+    /// The payload of the response (i.e., the bytes after the header) is written to `payload`.
+    fn send_command<Req: Pod>(&self, request: &Req, payload: &mut [u8]) -> Result<(), SoundError> {
+        let request_len = size_of::<Req>();
+        let response_len = size_of::<Header>() + payload.len();
+        if request_len > COMMAND_BUFFER_SIZE || response_len > COMMAND_BUFFER_SIZE {
+            return Err(SoundError::BadMessage);
+        }
+
+        let _guard = self.command_lock.lock();
+
+        let mut writer = self.request_buffer.writer().unwrap();
+        writer.write_val(request).unwrap();
+        self.request_buffer.sync_to_device(0..request_len).unwrap();
+
+        let request_slice = Slice::new(&self.request_buffer, 0..request_len);
+        let response_slice = Slice::new(&self.response_buffer, 0..response_len);
+        let mut control_queue = self.control_queue.disable_irq().lock();
+        control_queue
+            .add_dma_buf(&[&request_slice], &[&response_slice])
+            .unwrap();
+        if control_queue.should_notify() {
+            control_queue.notify();
+        }
+        drop(control_queue);
+
+        self.wait_queue.wait_until(|| {
+            let mut control_queue = self.control_queue.disable_irq().lock();
+            control_queue.pop_used().ok()
+        });
+
+        self.response_buffer
+            .sync_from_device(0..response_len)
+            .unwrap();
+        let mut reader = self.response_buffer.reader().unwrap();
+        let header: Header = reader.read_val().unwrap();
+        match Status::try_from(header.code) {
+            Ok(Status::Ok) => {
+                reader.read(&mut payload.into());
+                Ok(())
+            }
+            Ok(status) => Err(SoundError::from(status)),
+            Err(_) => Err(SoundError::IoError),
+        }
+    }
+}
+
+/// The buffer of an I/O message, which carries the PCM frames of a stream.
+///
+/// The buffer consists of the header, the data, and the status of the message.
+#[derive(Debug)]
+pub struct PcmBuffer {
+    stream_id: u32,
+    capacity: usize,
+    dma_stream: DmaStream,
+}
+
+impl PcmBuffer {
+    /// Allocates a buffer that can carry `capacity` bytes of the PCM frames of a stream.
+    pub fn new(stream_id: u32, capacity: usize) -> Result<Self, SoundError> {
+        let size = size_of::<PcmXfer>() + capacity + size_of::<PcmStatus>();
+        let dma_stream = DmaStream::alloc(size.div_ceil(PAGE_SIZE), false)
+            .map_err(|_| SoundError::OutOfMemory)?;
+
+        let buffer = Self {
+            stream_id,
+            capacity,
+            dma_stream,
+        };
+        let mut writer = buffer.dma_stream.writer().unwrap();
+        writer.write_val(&PcmXfer { stream_id }).unwrap();
+        buffer
+            .dma_stream
+            .sync_to_device(buffer.header_range())
+            .unwrap();
+
+        Ok(buffer)
+    }
+
+    /// Returns the ID of the stream.
+    pub fn stream_id(&self) -> u32 {
+        self.stream_id
+    }
+
+    /// Returns the maximum number of bytes of the PCM frames.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Writes the PCM frames at `offset` of the data.
+    pub fn write_data(&self, offset: usize, data: &[u8]) {
+        assert!(offset + data.len() <= self.capacity);
+
+        let range = self.data_range(offset + data.len());
+        let start = range.start + offset;
+        let mut writer = self.dma_stream.writer().unwrap();
+        writer.skip(start).write(&mut data.into());
+        self.dma_stream.sync_to_device(start..range.end).unwrap();
+    }
+
+    /// Reads the PCM frames at `offset` of the data.
+    ///
+    /// For the capture streams, the frames are valid only after the message is completed.
+    pub fn read_data(&self, offset: usize, data: &mut [u8]) {
+        assert!(offset + data.len() <= self.capacity);
+
+        let start = self.data_range(0).start + offset;
+        let mut reader = self.dma_stream.reader().unwrap();
+        reader.skip(start).read(&mut data.into());
+    }
+
+    /// Handles the completion of the message and returns its result.
+    ///
+    /// `used_len` is the number of bytes written by the device, including the status.
+    fn complete(&self, used_len: usize) -> Result<usize, SoundError> {
+        let status_range = self.status_range();
+        self.dma_stream
+            .sync_from_device(status_range.clone())
+            .unwrap();
+        let mut reader = self.dma_stream.reader().unwrap();
+        let status: PcmStatus = reader.skip(status_range.start).read_val().unwrap();
+        match Status::try_from(status.status) {
+            Ok(Status::Ok) => (),
+            Ok(status) => return Err(SoundError::from(status)),
+            Err(_) => return Err(SoundError::IoError),
+        }
+
+        // For the playback streams, only the status is written by the device.
+        let data_len = used_len
+            .saturating_sub(size_of::<PcmStatus>())
+            .min(self.capacity);
+        if data_len > 0 {
+            self.dma_stream
+                .sync_from_device(self.data_range(data_len))
+                .unwrap();
+        }
+        Ok(data_len)
+    }
+
+    fn header_range(&self) -> Range<usize> {
+        0..size_of::<PcmXfer>()
+    }
+
+    fn data_range(&self, len: usize) -> Range<usize> {
+        let start = size_of::<PcmXfer>();
+        start..start + len
+    }
+
+    fn status_range(&self) -> Range<usize> {
+        let start = size_of::<PcmXfer>() + self.capacity;
+        start..start + size_of::<PcmStatus>()
+    }
+}
@@ -0,0 +1,189 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The virtio sound device, which provides the PCM streams for playback and capture.
+//!
+//! The kernel configures the streams over the control queue and exchanges the PCM frames over the
+//! TX queue (for playback) and the RX queue (for capture). Neither the jacks, the channel maps,
+//! nor the control elements are supported.
+
+use alloc::{sync::Arc, vec::Vec};
+
+use int_to_c_enum::TryFromInt;
+use ostd::sync::SpinLock;
+
+pub use self::device::PcmBuffer;
+use self::{device::SoundDevice, protocol::Status};
+
+mod config;
+pub mod device;
+mod protocol;
+
+/// The direction of a PCM stream.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+pub enum PcmDirection {
+    /// The stream plays the frames to the host (i.e., playback).
+    Output = 0,
+    /// The stream records the frames from the host (i.e., capture).
+    Input = 1,
+}
+
+/// The sample format of a PCM stream.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+pub enum PcmFormat {
+    ImaAdpcm = 0,
+    MuLaw = 1,
+    ALaw = 2,
+    S8 = 3,
+    U8 = 4,
+    S16 = 5,
+    U16 = 6,
+    S18_3 = 7,
+    U18_3 = 8,
+    S20_3 = 9,
+    U20_3 = 10,
+    S24_3 = 11,
+    U24_3 = 12,
+    S20 = 13,
+    U20 = 14,
+    S24 = 15,
+    U24 = 16,
+    S32 = 17,
+    U32 = 18,
+    Float = 19,
+    Float64 = 20,
+    DsdU8 = 21,
+    DsdU16 = 22,
+    DsdU32 = 23,
+    Iec958Subframe = 24,
+}
+
+/// The frame rate of a PCM stream.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+pub enum PcmRate {
+    Rate5512 = 0,
+    Rate8000 = 1,
+    Rate11025 = 2,
+    Rate16000 = 3,
+    Rate22050 = 4,
+    Rate32000 = 5,
+    Rate44100 = 6,
+    Rate48000 = 7,
+    Rate64000 = 8,
+    Rate88200 = 9,
+    Rate96000 = 10,
+    Rate176400 = 11,
+    Rate192000 = 12,
+    Rate384000 = 13,
+}
+
+impl PcmRate {
+    /// Returns the number of frames per second.
+    pub fn hz(self) -> u32 {
+        match self {
+            Self::Rate5512 => 5512,
+            Self::Rate8000 => 8000,
+            Self::Rate11025 => 11025,
+            Self::Rate16000 => 16000,
+            Self::Rate22050 => 22050,
+            Self::Rate32000 => 32000,
+            Self::Rate44100 => 44100,
+            Self::Rate48000 => 48000,
+            Self::Rate64000 => 64000,
+            Self::Rate88200 => 88200,
+            Self::Rate96000 => 96000,
+            Self::Rate176400 => 176400,
+            Self::Rate192000 => 192000,
+            Self::Rate384000 => 384000,
+        }
+    }
+}
+
+/// The information of a PCM stream.
+#[derive(Debug, Clone, Copy)]
+pub struct PcmInfo {
+    /// The ID of the stream
+    pub stream_id: u32,
+    /// The ID of the function node in the HDA specification, which groups the streams
+    pub nid: u32,
+    /// The direction of the stream
+    pub direction: PcmDirection,
+    /// The supported sample formats, where bit `n` stands for the format whose value is `n`
+    pub formats: u64,
+    /// The supported frame rates, where bit `n` stands for the rate whose value is `n`
+    pub rates: u64,
+    /// The minimum number of channels
+    pub channels_min: u8,
+    /// The maximum number of channels
+    pub channels_max: u8,
+}
+
+impl PcmInfo {
+    /// Returns the supported sample formats.
+    pub fn formats(&self) -> impl Iterator<Item = PcmFormat> + '_ {
+        (0..u64::BITS as u8)
+            .filter(|bit| self.formats & (1 << bit) != 0)
+            .filter_map(|bit| PcmFormat::try_from(bit).ok())
+    }
+
+    /// Returns the supported frame rates.
+    pub fn rates(&self) -> impl Iterator<Item = PcmRate> + '_ {
+        (0..u64::BITS as u8)
+            .filter(|bit| self.rates & (1 << bit) != 0)
+            .filter_map(|bit| PcmRate::try_from(bit).ok())
+    }
+}
+
+/// The parameters of a PCM stream.
+#[derive(Debug, Clone, Copy)]
+pub struct PcmParams {
+    /// The size of the buffer in bytes
+    pub buffer_bytes: u32,
+    /// The size of a period in bytes
+    pub period_bytes: u32,
+    /// The number of channels
+    pub channels: u8,
+    /// The sample format
+    pub format: PcmFormat,
+    /// The frame rate
+    pub rate: PcmRate,
+}
+
+/// The errors reported by the device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoundError {
+    /// The request is malformed or contains invalid parameters.
+    BadMessage,
+    /// The operation is not supported.
+    NotSupported,
+    /// The device fails to perform the operation.
+    IoError,
+    /// The DMA buffers cannot be allocated.
+    OutOfMemory,
+    /// There are too many pending I/O messages.
+    QueueFull,
+}
+
+impl From<Status> for SoundError {
+    fn from(status: Status) -> Self {
+        match status {
+            Status::BadMsg => Self::BadMessage,
+            Status::NotSupp => Self::NotSupported,
+            Status::Ok | Status::IoErr => Self::IoError,
+        }
+    }
+}
+
+/// Registers a device.
+pub fn register_device(device: Arc<SoundDevice>) {
+    SOUND_DEVICES.lock().push(device);
+}
+
+/// Returns all the devices in the order in which they are found.
+pub fn all_devices() -> Vec<Arc<SoundDevice>> {
+    SOUND_DEVICES.lock().clone()
+}
+
+static SOUND_DEVICES: SpinLock<Vec<Arc<SoundDevice>>> = SpinLock::new(Vec::new());
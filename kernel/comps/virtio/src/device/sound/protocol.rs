@@ -0,0 +1,116 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The messages that are exchanged over the control, TX, and RX virtqueues.
+//!
+//! Only the messages for PCM streams are defined here.
+//!
+//! Reference: The VirtIO spec 5.14.6 Device Operation.
+
+use int_to_c_enum::TryFromInt;
+
+/// The code of a request.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum RequestCode {
+    PcmInfo = 0x0100,
+    PcmSetParams = 0x0101,
+    PcmPrepare = 0x0102,
+    PcmRelease = 0x0103,
+    PcmStart = 0x0104,
+    PcmStop = 0x0105,
+}
+
+/// The status of a response.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+pub(super) enum Status {
+    Ok = 0x8000,
+    BadMsg = 0x8001,
+    NotSupp = 0x8002,
+    IoErr = 0x8003,
+}
+
+/// The header of all requests and responses; `struct virtio_snd_hdr` in the spec.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+pub(super) struct Header {
+    pub(super) code: u32,
+}
+
+impl Header {
+    pub(super) fn new(code: RequestCode) -> Self {
+        Self { code: code as u32 }
+    }
+}
+
+/// The request to query the information of the items; `struct virtio_snd_query_info` in the
+/// spec.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+pub(super) struct QueryInfo {
+    pub(super) header: Header,
+    pub(super) start_id: u32,
+    pub(super) count: u32,
+    pub(super) size: u32,
+}
+
+/// The information of a PCM stream; `struct virtio_snd_pcm_info` in the spec.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+pub(super) struct PcmInfoRaw {
+    pub(super) hda_fn_nid: u32,
+    pub(super) features: u32,
+    pub(super) formats: u64,
+    pub(super) rates: u64,
+    pub(super) direction: u8,
+    pub(super) channels_min: u8,
+    pub(super) channels_max: u8,
+    pub(super) padding: [u8; 5],
+}
+
+/// The header of the requests for a PCM stream; `struct virtio_snd_pcm_hdr` in the spec.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+pub(super) struct PcmHeader {
+    pub(super) header: Header,
+    pub(super) stream_id: u32,
+}
+
+impl PcmHeader {
+    pub(super) fn new(code: RequestCode, stream_id: u32) -> Self {
+        Self {
+            header: Header::new(code),
+            stream_id,
+        }
+    }
+}
+
+/// The request to set the parameters of a PCM stream; `struct virtio_snd_pcm_set_params` in
+/// the spec.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+pub(super) struct PcmSetParams {
+    pub(super) pcm_header: PcmHeader,
+    pub(super) buffer_bytes: u32,
+    pub(super) period_bytes: u32,
+    pub(super) features: u32,
+    pub(super) channels: u8,
+    pub(super) format: u8,
+    pub(super) rate: u8,
+    pub(super) padding: u8,
+}
+
+/// The header of an I/O message; `struct virtio_snd_pcm_xfer` in the spec.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+pub(super) struct PcmXfer {
+    pub(super) stream_id: u32,
+}
+
+/// The status of an I/O message; `struct virtio_snd_pcm_status` in the spec.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+pub(super) struct PcmStatus {
+    pub(super) status: u32,
+    pub(super) latency_bytes: u32,
+}
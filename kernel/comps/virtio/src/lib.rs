@@ -18,7 +18,9 @@ use bitflags::bitflags;
 use component::{ComponentInitError, init_component};
 use device::{
     VirtioDeviceType, block::device::BlockDevice, console::device::ConsoleDevice,
-    input::device::InputDevice, network::device::NetworkDevice, socket::device::SocketDevice,
+    entropy::device::EntropyDevice, filesystem::device::FileSystemDevice, gpu::device::GpuDevice,
+    input::device::InputDevice, network::device::NetworkDevice, scsi::device::ScsiDevice,
+    socket::device::SocketDevice, sound::device::SoundDevice,
 };
 use log::{error, warn};
 use spin::Once;
@@ -43,6 +45,7 @@ fn virtio_component_init() -> Result<(), ComponentInitError> {
 
     device::network::init();
     device::socket::init();
+    device::filesystem::init();
 
     while let Some(mut transport) = pop_device_transport() {
         // Reset device
@@ -73,7 +76,12 @@ fn virtio_component_init() -> Result<(), ComponentInitError> {
             VirtioDeviceType::Input => InputDevice::init(transport),
             VirtioDeviceType::Network => NetworkDevice::init(transport),
             VirtioDeviceType::Console => ConsoleDevice::init(transport),
+            VirtioDeviceType::Entropy => EntropyDevice::init(transport),
             VirtioDeviceType::Socket => SocketDevice::init(transport),
+            VirtioDeviceType::FileSystem => FileSystemDevice::init(transport),
+            VirtioDeviceType::ScsiHost => ScsiDevice::init(transport),
+            VirtioDeviceType::Gpu => GpuDevice::init(transport),
+            VirtioDeviceType::Sound => SoundDevice::init(transport),
             _ => {
                 warn!("[Virtio]: Found unimplemented device:{:?}", device_type);
                 Ok(())
@@ -108,7 +116,14 @@ fn negotiate_features(transport: &mut Box<dyn VirtioTransport>) {
         VirtioDeviceType::Block => BlockDevice::negotiate_features(device_specified_features),
         VirtioDeviceType::Input => InputDevice::negotiate_features(device_specified_features),
         VirtioDeviceType::Console => ConsoleDevice::negotiate_features(device_specified_features),
+        VirtioDeviceType::Entropy => EntropyDevice::negotiate_features(device_specified_features),
         VirtioDeviceType::Socket => SocketDevice::negotiate_features(device_specified_features),
+        VirtioDeviceType::FileSystem => {
+            FileSystemDevice::negotiate_features(device_specified_features)
+        }
+        VirtioDeviceType::ScsiHost => ScsiDevice::negotiate_features(device_specified_features),
+        VirtioDeviceType::Gpu => GpuDevice::negotiate_features(device_specified_features),
+        VirtioDeviceType::Sound => SoundDevice::negotiate_features(device_specified_features),
         _ => device_specified_features,
     };
     let mut support_feature = Feature::from_bits_truncate(features);
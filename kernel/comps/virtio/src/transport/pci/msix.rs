@@ -2,7 +2,7 @@
 
 use alloc::vec::Vec;
 
-use aster_pci::capability::msix::CapabilityMsixData;
+use aster_pci::capability::msix::{CapabilityMsixData, IrqAffinity};
 use ostd::irq::IrqLine;
 
 pub struct VirtioMsixManager {
@@ -19,11 +19,16 @@ pub struct VirtioMsixManager {
 
 impl VirtioMsixManager {
     pub fn new(mut msix: CapabilityMsixData) -> Self {
-        let mut msix_vector_list: Vec<u16> = (0..msix.table_size()).collect();
-        for i in msix_vector_list.iter() {
-            let irq = IrqLine::alloc().unwrap();
-            msix.set_interrupt_vector(irq, *i);
-        }
+        // Spread the vectors used by single queues across CPUs. The last two vectors are used for
+        // the configuration changes and shared by the queues, respectively.
+        let nr_vectors = msix.alloc_irq_vectors(
+            msix.table_size(),
+            &IrqAffinity {
+                pre_vectors: 0,
+                post_vectors: 2,
+            },
+        );
+        let mut msix_vector_list: Vec<u16> = (0..nr_vectors).collect();
         let config_msix_vector = msix_vector_list.pop().unwrap();
         let shared_interrupt_vector = msix_vector_list.pop().unwrap();
         Self {
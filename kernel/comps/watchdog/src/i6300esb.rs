@@ -0,0 +1,273 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The driver of the Intel 6300ESB watchdog timer, which is emulated by QEMU.
+//!
+//! Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/watchdog/i6300esb.c>.
+
+use alloc::{collections::vec_deque::VecDeque, format, sync::Arc};
+use core::ops::RangeInclusive;
+
+use aster_pci::{
+    PCI_BUS, PciDeviceId, PciDeviceLocation,
+    bus::{PciDevice, PciDriver},
+    cfg_space::{Bar, Command},
+    common_device::PciCommonDevice,
+};
+use log::{info, warn};
+use ostd::{
+    bus::BusProbeError,
+    io::IoMem,
+    mm::VmIoOnce,
+    sync::{LocalIrqDisabled, SpinLock},
+};
+use spin::Once;
+
+use crate::{AnyWatchdogDevice, WatchdogFlags};
+
+const VENDOR_ID_INTEL: u16 = 0x8086;
+const DEVICE_ID_ESB_9: u16 = 0x25ab;
+
+/// The offsets of the registers in the PCI configuration space.
+mod cfg {
+    /// The configuration register.
+    pub(super) const CONFIG: u16 = 0x60;
+    /// The lock register.
+    pub(super) const LOCK: u16 = 0x68;
+}
+
+/// The offsets of the memory-mapped registers.
+mod reg {
+    /// The preload value of the first stage.
+    pub(super) const TIMER1: usize = 0x00;
+    /// The preload value of the second stage.
+    pub(super) const TIMER2: usize = 0x04;
+    /// The reload register.
+    pub(super) const RELOAD: usize = 0x0c;
+}
+
+/// The configuration that disables the interrupt of the first stage.
+///
+/// The timer frequency is set to about 1 kHz and the reset output is enabled.
+const CONFIG_INT_DISABLED: u16 = 0x0003;
+
+/// The bit in the lock register that enables the watchdog timer.
+const LOCK_ENABLE: u8 = 1 << 1;
+/// The bit in the lock register that prevents the watchdog timer from being stopped.
+const LOCK_LOCK: u8 = 1 << 0;
+
+/// The bit in the reload register that indicates the timeout of the second stage.
+const RELOAD_TIMEOUT: u16 = 1 << 9;
+/// The bit in the reload register that reloads the timer.
+const RELOAD_RELOAD: u16 = 1 << 8;
+
+/// The values that must be written to the reload register before writing any register.
+const UNLOCK_SEQUENCE: [u16; 2] = [0x80, 0x86];
+
+/// The default timeout in seconds.
+const DEFAULT_TIMEOUT: u32 = 30;
+/// The range of the timeout in seconds.
+///
+/// The timer has 20 bits and the preload value is the timeout multiplied by 512. Since the timer
+/// counts down both stages, the system is reset after about `1024 * timeout` ticks of 1 kHz.
+const TIMEOUT_RANGE: RangeInclusive<u32> = 1..=2046;
+
+static I6300ESB_PCI_DRIVER: Once<Arc<I6300EsbPciDriver>> = Once::new();
+
+pub(crate) fn init() {
+    I6300ESB_PCI_DRIVER.call_once(|| Arc::new(I6300EsbPciDriver::new()));
+    PCI_BUS
+        .lock()
+        .register_driver(I6300ESB_PCI_DRIVER.get().unwrap().clone());
+
+    let mut index = 0;
+    while let Some(device) = I6300ESB_PCI_DRIVER.get().unwrap().pop_device() {
+        let Some(watchdog) = I6300Esb::init(&device) else {
+            warn!(
+                "failed to initialize the i6300ESB watchdog at {:?}",
+                device.location()
+            );
+            continue;
+        };
+
+        crate::register_device(format!("i6300esb{}", index), Arc::new(watchdog));
+        index += 1;
+    }
+}
+
+/// An Intel 6300ESB watchdog timer.
+#[derive(Debug)]
+struct I6300Esb {
+    location: PciDeviceLocation,
+    regs: IoMem,
+    /// The timeout in seconds.
+    ///
+    /// The lock also serializes the accesses to the registers, since each register write must
+    /// follow the unlock sequence.
+    timeout: SpinLock<u32, LocalIrqDisabled>,
+    boot_status: WatchdogFlags,
+}
+
+impl I6300Esb {
+    fn init(device: &PciCommonDevice) -> Option<Self> {
+        let Some(Bar::Memory(bar)) = device.bar_manager().bar(0) else {
+            return None;
+        };
+        let regs = bar.io_mem().clone();
+        device.write_command(device.read_command() | Command::MEMORY_SPACE);
+
+        let location = *device.location();
+        location.write16(cfg::CONFIG, CONFIG_INT_DISABLED);
+
+        if location.read8(cfg::LOCK) & LOCK_LOCK != 0 {
+            warn!("the i6300ESB watchdog is locked and cannot be stopped");
+        }
+        // Disable the watchdog timer for now.
+        location.write8(cfg::LOCK, 0);
+
+        let mut watchdog = Self {
+            location,
+            regs,
+            timeout: SpinLock::new(DEFAULT_TIMEOUT),
+            boot_status: WatchdogFlags::empty(),
+        };
+
+        // Check whether the last reset of the system was caused by the watchdog timer.
+        watchdog.unlock_registers();
+        let reload: u16 = watchdog.regs.read_once(reg::RELOAD).ok()?;
+        if reload & RELOAD_TIMEOUT != 0 {
+            watchdog.boot_status = WatchdogFlags::CARDRESET;
+        }
+
+        // Clear the timeout flag and reload the timer.
+        watchdog.unlock_registers();
+        watchdog.write_reload(RELOAD_TIMEOUT | RELOAD_RELOAD);
+
+        watchdog.write_timeout(DEFAULT_TIMEOUT);
+
+        info!(
+            "i6300ESB watchdog initialized at {:?}, timeout = {} s",
+            location, DEFAULT_TIMEOUT
+        );
+
+        Some(watchdog)
+    }
+
+    fn unlock_registers(&self) {
+        for value in UNLOCK_SEQUENCE {
+            self.write_reload(value);
+        }
+    }
+
+    fn write_reload(&self, value: u16) {
+        self.regs.write_once(reg::RELOAD, &value).unwrap();
+    }
+
+    fn write_timeout(&self, timeout: u32) {
+        let preload = timeout << 9;
+
+        self.unlock_registers();
+        self.regs.write_once(reg::TIMER1, &preload).unwrap();
+        self.unlock_registers();
+        self.regs.write_once(reg::TIMER2, &preload).unwrap();
+        self.unlock_registers();
+        self.write_reload(RELOAD_RELOAD);
+    }
+}
+
+impl AnyWatchdogDevice for I6300Esb {
+    fn identity(&self) -> &str {
+        "i6300ESB timer"
+    }
+
+    fn options(&self) -> WatchdogFlags {
+        WatchdogFlags::SETTIMEOUT | WatchdogFlags::KEEPALIVEPING | WatchdogFlags::MAGICCLOSE
+    }
+
+    fn boot_status(&self) -> WatchdogFlags {
+        self.boot_status
+    }
+
+    fn start(&self) {
+        let _guard = self.timeout.lock();
+
+        self.unlock_registers();
+        self.write_reload(RELOAD_RELOAD);
+        self.location.write8(cfg::LOCK, LOCK_ENABLE);
+    }
+
+    fn stop(&self) {
+        let _guard = self.timeout.lock();
+
+        self.unlock_registers();
+        self.write_reload(RELOAD_RELOAD);
+        self.location.write8(cfg::LOCK, 0);
+    }
+
+    fn ping(&self) {
+        let _guard = self.timeout.lock();
+
+        self.unlock_registers();
+        self.write_reload(RELOAD_RELOAD);
+    }
+
+    fn timeout(&self) -> u32 {
+        *self.timeout.lock()
+    }
+
+    fn timeout_range(&self) -> RangeInclusive<u32> {
+        TIMEOUT_RANGE
+    }
+
+    fn set_timeout(&self, timeout: u32) {
+        let mut guard = self.timeout.lock();
+
+        self.write_timeout(timeout);
+        *guard = timeout;
+    }
+}
+
+/// The PCI driver that claims the i6300ESB watchdog timers.
+///
+/// The claimed devices are initialized later by the component.
+#[derive(Debug)]
+struct I6300EsbPciDriver {
+    devices: SpinLock<VecDeque<PciCommonDevice>>,
+}
+
+impl I6300EsbPciDriver {
+    fn new() -> Self {
+        Self {
+            devices: SpinLock::new(VecDeque::new()),
+        }
+    }
+
+    fn pop_device(&self) -> Option<PciCommonDevice> {
+        self.devices.lock().pop_front()
+    }
+}
+
+impl PciDriver for I6300EsbPciDriver {
+    fn probe(
+        &self,
+        device: PciCommonDevice,
+    ) -> Result<Arc<dyn PciDevice>, (BusProbeError, PciCommonDevice)> {
+        let device_id = *device.device_id();
+        if (device_id.vendor_id, device_id.device_id) != (VENDOR_ID_INTEL, DEVICE_ID_ESB_9) {
+            return Err((BusProbeError::DeviceNotMatch, device));
+        }
+        self.devices.lock().push_back(device);
+
+        Ok(Arc::new(I6300EsbPciDevice { device_id }))
+    }
+}
+
+#[derive(Debug)]
+struct I6300EsbPciDevice {
+    device_id: PciDeviceId,
+}
+
+impl PciDevice for I6300EsbPciDevice {
+    fn device_id(&self) -> PciDeviceId {
+        self.device_id
+    }
+}
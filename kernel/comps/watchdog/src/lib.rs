@@ -0,0 +1,148 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The watchdog timers of Asterinas.
+//!
+//! The drivers of watchdog timers register their devices here. The kernel exposes the current
+//! device as `/dev/watchdog`. Once started, a watchdog timer resets the system unless it is pinged
+//! before its timeout expires.
+
+#![no_std]
+#![deny(unsafe_code)]
+
+extern crate alloc;
+
+mod i6300esb;
+
+use alloc::{collections::BTreeMap, fmt::Debug, string::String, sync::Arc, vec::Vec};
+use core::{any::Any, ops::RangeInclusive};
+
+use bitflags::bitflags;
+use component::{ComponentInitError, init_component};
+use ostd::sync::SpinLock;
+use spin::Once;
+
+bitflags! {
+    /// The options supported by a watchdog timer, which are also used to report its status.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/watchdog.h>.
+    pub struct WatchdogFlags: u32 {
+        /// The device reports that it is overheated.
+        const OVERHEAT      = 0x0001;
+        /// The device reports that a fan has failed.
+        const FANFAULT      = 0x0002;
+        /// The device reports an external relay 1.
+        const EXTERN1       = 0x0004;
+        /// The device reports an external relay 2.
+        const EXTERN2       = 0x0008;
+        /// The device reports that the power is bad or the power has failed.
+        const POWERUNDER    = 0x0010;
+        /// The last reset of the system was caused by the device.
+        const CARDRESET     = 0x0020;
+        /// The device reports that the power is over voltage.
+        const POWEROVER     = 0x0040;
+        /// The timeout of the device can be set.
+        const SETTIMEOUT    = 0x0080;
+        /// The device supports the magic close character.
+        const MAGICCLOSE    = 0x0100;
+        /// The pretimeout of the device can be set.
+        const PRETIMEOUT    = 0x0200;
+        /// The device triggers a management or other external alarm instead of a reset.
+        const ALARMONLY     = 0x0400;
+        /// The device can be pinged with the `WDIOC_KEEPALIVE` ioctl.
+        const KEEPALIVEPING = 0x8000;
+    }
+}
+
+pub trait AnyWatchdogDevice: Send + Sync + Any + Debug {
+    /// Returns the identity of the device, which is reported to user space.
+    fn identity(&self) -> &str;
+
+    /// Returns the options supported by the device.
+    fn options(&self) -> WatchdogFlags;
+
+    /// Returns the status of the device at boot.
+    ///
+    /// For example, [`WatchdogFlags::CARDRESET`] is set if the last reset of the system was
+    /// caused by the device.
+    fn boot_status(&self) -> WatchdogFlags {
+        WatchdogFlags::empty()
+    }
+
+    /// Starts the watchdog timer.
+    fn start(&self);
+
+    /// Stops the watchdog timer.
+    fn stop(&self);
+
+    /// Pings the watchdog timer, which restarts its countdown.
+    fn ping(&self);
+
+    /// Returns the timeout in seconds.
+    fn timeout(&self) -> u32;
+
+    /// Returns the range of the timeout in seconds.
+    fn timeout_range(&self) -> RangeInclusive<u32>;
+
+    /// Sets the timeout in seconds.
+    ///
+    /// The caller should ensure that the timeout is in [`Self::timeout_range`]. If the watchdog
+    /// timer is running, the new timeout takes effect after the next ping.
+    fn set_timeout(&self, timeout: u32);
+
+    /// Returns the remaining time in seconds before the system is reset.
+    ///
+    /// This method returns `None` if the device cannot report the remaining time.
+    fn time_left(&self) -> Option<u32> {
+        None
+    }
+}
+
+pub fn register_device(name: String, device: Arc<dyn AnyWatchdogDevice>) {
+    COMPONENT
+        .get()
+        .unwrap()
+        .watchdog_device_table
+        .lock()
+        .insert(name, device);
+}
+
+pub fn all_devices() -> Vec<(String, Arc<dyn AnyWatchdogDevice>)> {
+    let watchdog_devices = COMPONENT.get().unwrap().watchdog_device_table.lock();
+    watchdog_devices
+        .iter()
+        .map(|(name, device)| (name.clone(), device.clone()))
+        .collect()
+}
+
+/// Returns the device with the smallest name, if any.
+pub fn current_device() -> Option<(String, Arc<dyn AnyWatchdogDevice>)> {
+    let watchdog_devices = COMPONENT.get().unwrap().watchdog_device_table.lock();
+    watchdog_devices
+        .first_key_value()
+        .map(|(name, device)| (name.clone(), device.clone()))
+}
+
+static COMPONENT: Once<Component> = Once::new();
+
+#[init_component]
+fn component_init() -> Result<(), ComponentInitError> {
+    let component = Component::init()?;
+    COMPONENT.call_once(|| component);
+
+    i6300esb::init();
+
+    Ok(())
+}
+
+#[derive(Debug)]
+struct Component {
+    watchdog_device_table: SpinLock<BTreeMap<String, Arc<dyn AnyWatchdogDevice>>>,
+}
+
+impl Component {
+    pub fn init() -> Result<Self, ComponentInitError> {
+        Ok(Self {
+            watchdog_device_table: SpinLock::new(BTreeMap::new()),
+        })
+    }
+}
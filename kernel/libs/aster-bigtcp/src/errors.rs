@@ -112,3 +112,40 @@ pub mod udp {
         }
     }
 }
+
+pub mod raw {
+    /// An error returned by [`RawIpSocket::send`].
+    ///
+    /// [`RawIpSocket::send`]: crate::socket::RawIpSocket::send
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub enum SendError {
+        BufferFull,
+        /// The packet is too large.
+        TooLarge,
+        /// The ICMP header of a ping socket or the IP header provided by the user is invalid.
+        InvalidHeader,
+    }
+
+    /// An error returned by [`RawIpSocket::recv`].
+    ///
+    /// [`RawIpSocket::recv`]: crate::socket::RawIpSocket::recv
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub enum RecvError {
+        /// The receive queue is empty.
+        Exhausted,
+    }
+}
+
+pub mod packet {
+    /// An error returned by [`PacketSocket::send`].
+    ///
+    /// [`PacketSocket::send`]: crate::socket::PacketSocket::send
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub enum SendError {
+        BufferFull,
+        /// The frame is too large.
+        TooLarge,
+        /// The frame is too short to contain an Ethernet header.
+        InvalidHeader,
+    }
+}
@@ -1,6 +1,11 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use crate::{iface::ScheduleNextPoll, socket::SocketEventObserver};
+use crate::{
+    filter::PacketFilter,
+    iface::ScheduleNextPoll,
+    socket::{FrameObserver, SocketEventObserver},
+    time::WallClock,
+};
 
 /// Extension to be implemented by users of this crate.
 ///
@@ -19,4 +24,16 @@ pub trait Ext {
 
     /// The type for UDP sockets to observe events.
     type UdpEventObserver: SocketEventObserver;
+
+    /// The type for raw sockets to observe events.
+    type RawEventObserver: SocketEventObserver;
+
+    /// The type for packet sockets to observe events and frames.
+    type PacketEventObserver: FrameObserver;
+
+    /// The type for the IP stack to filter packets.
+    type PacketFilter: PacketFilter;
+
+    /// The type for sockets to timestamp the received packets.
+    type WallClock: WallClock;
 }
@@ -0,0 +1,245 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Packet filtering hooks.
+//!
+//! Like the netfilter hooks in Linux, the hooks are the points in the IP stack where the packets
+//! are passed to a [`PacketFilter`], which decides whether the packets should be accepted or
+//! dropped. The filter can also translate the addresses and the ports of the packets (i.e., NAT).
+
+use smoltcp::{
+    iface::packet::{IpPayload, Packet},
+    wire::{IpAddress, IpProtocol, IpRepr, Ipv4Address, TcpControl, TcpRepr, UdpRepr},
+};
+
+use crate::ext::Ext;
+
+/// A point in the IP stack where the packets are filtered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    /// The hook for the received packets before they are routed.
+    PreRouting,
+    /// The hook for the received packets that are destined for the local host.
+    Input,
+    /// The hook for the received packets that are forwarded to other hosts.
+    ///
+    /// Forwarding is not supported yet, so no packets go through this hook.
+    Forward,
+    /// The hook for the packets that are generated by the local host.
+    Output,
+    /// The hook for the packets that are about to be sent.
+    PostRouting,
+}
+
+/// The decision of a [`PacketFilter`] about a packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Accept,
+    Drop,
+}
+
+bitflags::bitflags! {
+    /// The flags of a TCP segment.
+    pub struct TcpFlags: u8 {
+        const FIN = 0x01;
+        const SYN = 0x02;
+        const RST = 0x04;
+        const PSH = 0x08;
+        const ACK = 0x10;
+    }
+}
+
+/// The iface that a packet is received from or sent to.
+#[derive(Debug, Clone, Copy)]
+pub struct PacketIface {
+    pub index: u32,
+    /// The IPv4 address of the iface.
+    ///
+    /// This is provided because the filter cannot query the iface while the iface is being
+    /// polled.
+    pub ipv4_addr: Option<Ipv4Address>,
+}
+
+/// The information of a packet that is passed to a [`PacketFilter`].
+///
+/// The filter translates the packet by modifying the addresses and the ports. Currently, the
+/// translation only takes effect for TCP and UDP packets.
+#[derive(Debug, Clone, Copy)]
+pub struct PacketInfo {
+    /// The iface of the packet.
+    ///
+    /// For [`Hook::PreRouting`] and [`Hook::Input`], this is the iface that receives the packet.
+    /// Otherwise, this is the iface that sends the packet.
+    pub iface: PacketIface,
+    pub src_addr: IpAddress,
+    pub dst_addr: IpAddress,
+    pub protocol: IpProtocol,
+    /// The source and destination ports, if the packet is a TCP or UDP packet.
+    pub ports: Option<(u16, u16)>,
+    /// The flags, if the packet is a TCP packet.
+    pub tcp_flags: TcpFlags,
+    /// The connection tracking state of the packet.
+    ///
+    /// The IP stack sets it to zero before the packet goes through the first hook. The filter can
+    /// use it to pass information between the hooks that the packet goes through.
+    pub conntrack: u64,
+}
+
+impl PacketInfo {
+    pub(crate) fn new(
+        iface: PacketIface,
+        ip_repr: &IpRepr,
+        ports: Option<(u16, u16)>,
+        tcp_flags: TcpFlags,
+    ) -> Self {
+        Self {
+            iface,
+            src_addr: ip_repr.src_addr(),
+            dst_addr: ip_repr.dst_addr(),
+            protocol: ip_repr.next_header(),
+            ports,
+            tcp_flags,
+            conntrack: 0,
+        }
+    }
+
+    /// Creates the information from the IP header and the raw IP payload.
+    pub(crate) fn parse(iface: PacketIface, ip_repr: &IpRepr, ip_payload: &[u8]) -> Self {
+        // Both TCP and UDP headers start with the source port and the destination port.
+        let ports = match (ip_repr.next_header(), ip_payload) {
+            (IpProtocol::Tcp | IpProtocol::Udp, [src0, src1, dst0, dst1, ..]) => Some((
+                u16::from_be_bytes([*src0, *src1]),
+                u16::from_be_bytes([*dst0, *dst1]),
+            )),
+            _ => None,
+        };
+        let tcp_flags = match (ip_repr.next_header(), ip_payload.get(TCP_FLAGS_OFFSET)) {
+            (IpProtocol::Tcp, Some(flags)) => TcpFlags::from_bits_truncate(*flags),
+            _ => TcpFlags::empty(),
+        };
+
+        Self::new(iface, ip_repr, ports, tcp_flags)
+    }
+
+    /// Creates the information from a TCP packet.
+    pub(crate) fn from_tcp(iface: PacketIface, ip_repr: &IpRepr, tcp_repr: &TcpRepr) -> Self {
+        let mut tcp_flags = match tcp_repr.control {
+            TcpControl::None => TcpFlags::empty(),
+            TcpControl::Psh => TcpFlags::PSH,
+            TcpControl::Syn => TcpFlags::SYN,
+            TcpControl::Fin => TcpFlags::FIN,
+            TcpControl::Rst => TcpFlags::RST,
+        };
+        if tcp_repr.ack_number.is_some() {
+            tcp_flags |= TcpFlags::ACK;
+        }
+
+        let ports = (tcp_repr.src_port, tcp_repr.dst_port);
+        Self::new(iface, ip_repr, Some(ports), tcp_flags)
+    }
+
+    /// Creates the information from a UDP packet.
+    pub(crate) fn from_udp(iface: PacketIface, ip_repr: &IpRepr, udp_repr: &UdpRepr) -> Self {
+        let ports = (udp_repr.src_port, udp_repr.dst_port);
+        Self::new(iface, ip_repr, Some(ports), TcpFlags::empty())
+    }
+
+    /// Creates the information from a packet that is about to be sent.
+    pub(crate) fn from_packet(iface: PacketIface, pkt: &Packet) -> Self {
+        let ip_repr = pkt.ip_repr();
+
+        match pkt.payload() {
+            IpPayload::Tcp(tcp_repr) => Self::from_tcp(iface, &ip_repr, tcp_repr),
+            IpPayload::Udp(udp_repr, _) => Self::from_udp(iface, &ip_repr, udp_repr),
+            IpPayload::Raw(ip_payload) => Self::parse(iface, &ip_repr, ip_payload),
+            _ => Self::new(iface, &ip_repr, None, TcpFlags::empty()),
+        }
+    }
+
+    /// Returns the IP header with the translated addresses.
+    pub(crate) fn translate_ip_repr(&self, ip_repr: &IpRepr) -> IpRepr {
+        let mut ip_repr = ip_repr.clone();
+        match (&mut ip_repr, self.src_addr, self.dst_addr) {
+            (IpRepr::Ipv4(ipv4_repr), IpAddress::Ipv4(src_addr), IpAddress::Ipv4(dst_addr)) => {
+                ipv4_repr.src_addr = src_addr;
+                ipv4_repr.dst_addr = dst_addr;
+            }
+            (IpRepr::Ipv6(ipv6_repr), IpAddress::Ipv6(src_addr), IpAddress::Ipv6(dst_addr)) => {
+                ipv6_repr.src_addr = src_addr;
+                ipv6_repr.dst_addr = dst_addr;
+            }
+            // The filter should never change the IP version.
+            _ => (),
+        }
+        ip_repr
+    }
+
+    /// Returns the TCP packet with the translated addresses and ports.
+    pub(crate) fn translate_tcp<'a>(
+        &self,
+        ip_repr: &IpRepr,
+        tcp_repr: &TcpRepr<'a>,
+    ) -> (IpRepr, TcpRepr<'a>) {
+        let (src_port, dst_port) = self.ports.unwrap_or((tcp_repr.src_port, tcp_repr.dst_port));
+        let tcp_repr = TcpRepr {
+            src_port,
+            dst_port,
+            ..*tcp_repr
+        };
+        (self.translate_ip_repr(ip_repr), tcp_repr)
+    }
+
+    /// Returns the UDP packet with the translated addresses and ports.
+    pub(crate) fn translate_udp(&self, ip_repr: &IpRepr, udp_repr: &UdpRepr) -> (IpRepr, UdpRepr) {
+        let (src_port, dst_port) = self.ports.unwrap_or((udp_repr.src_port, udp_repr.dst_port));
+        let udp_repr = UdpRepr { src_port, dst_port };
+        (self.translate_ip_repr(ip_repr), udp_repr)
+    }
+
+    /// Returns the packet with the translated addresses and ports.
+    ///
+    /// This method returns `None` if the packet is not translated.
+    pub(crate) fn translate_packet<'p>(&self, pkt: &Packet<'p>) -> Option<Packet<'p>> {
+        let ip_repr = pkt.ip_repr();
+
+        let (ip_repr, payload) = match pkt.payload() {
+            IpPayload::Tcp(tcp_repr) => {
+                let (new_ip_repr, new_tcp_repr) = self.translate_tcp(&ip_repr, tcp_repr);
+                if new_ip_repr == ip_repr && new_tcp_repr == *tcp_repr {
+                    return None;
+                }
+                (new_ip_repr, IpPayload::Tcp(new_tcp_repr))
+            }
+            IpPayload::Udp(udp_repr, udp_payload) => {
+                let (new_ip_repr, new_udp_repr) = self.translate_udp(&ip_repr, udp_repr);
+                if new_ip_repr == ip_repr && new_udp_repr == *udp_repr {
+                    return None;
+                }
+                (new_ip_repr, IpPayload::Udp(new_udp_repr, *udp_payload))
+            }
+            // TODO: Translate other packets (e.g., ICMP echo requests).
+            _ => return None,
+        };
+
+        Some(Packet::new(ip_repr, payload))
+    }
+}
+
+/// A filter that decides whether the packets should be accepted or dropped.
+pub trait PacketFilter {
+    /// Decides whether the packet should be accepted or dropped at the hook.
+    ///
+    /// The filter may also translate the packet by modifying `packet`.
+    fn filter(hook: Hook, packet: &mut PacketInfo) -> Verdict;
+}
+
+/// Passes the packet to the hooks in order.
+///
+/// This method returns whether the packet is accepted by all the hooks.
+pub(crate) fn run_hooks<E: Ext>(hooks: &[Hook], packet: &mut PacketInfo) -> bool {
+    hooks
+        .iter()
+        .all(|hook| E::PacketFilter::filter(*hook, packet) == Verdict::Accept)
+}
+
+/// The offset of the flags in the TCP header.
+const TCP_FLAGS_OFFSET: usize = 13;
@@ -1,9 +1,11 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use alloc::{
+    borrow::Cow,
     collections::btree_map::{BTreeMap, Entry},
     string::String,
     sync::Arc,
+    vec,
     vec::Vec,
 };
 use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
@@ -14,21 +16,27 @@ use int_to_c_enum::TryFromInt;
 use ostd::sync::{SpinLock, SpinLockGuard};
 use smoltcp::{
     iface::{Context, packet::Packet},
-    phy::Device,
-    wire::{IpAddress, IpEndpoint, Ipv4Address, Ipv4Packet},
+    phy::{Device, Medium, TxToken},
+    time::Instant,
+    wire::{
+        ETHERNET_HEADER_LEN, EthernetAddress, EthernetFrame, EthernetProtocol, IpAddress,
+        IpEndpoint, IpVersion, Ipv4Address, Ipv4Cidr, Ipv6Address, Ipv6Cidr,
+    },
 };
 
 use super::{
     Iface,
-    poll::{FnHelper, PollContext, SocketTableAction},
+    multicast::Ipv4MulticastGroups,
+    poll::{FnHelper, IpPacket, PollContext, SocketTableAction},
     poll_iface::PollableIface,
-    port::BindPortConfig,
+    port::{BindPortConfig, PortReuse},
     time::get_network_timestamp,
 };
 use crate::{
     errors::BindError,
     ext::Ext,
-    socket::{TcpListenerBg, UdpSocketBg},
+    filter::{Hook, PacketIface, PacketInfo, run_hooks},
+    socket::{FrameType, PacketSocketBg, RawIpSocketBg, TcpListenerBg, UdpSocketBg},
     socket_table::SocketTable,
 };
 
@@ -41,6 +49,8 @@ pub struct IfaceCommon<E: Ext> {
     interface: SpinLock<PollableIface<E>, BottomHalfDisabled>,
     used_ports: SpinLock<BTreeMap<u16, PortState>, BottomHalfDisabled>,
     sockets: SpinLock<SocketTable<E>, BottomHalfDisabled>,
+    multicast_groups: SpinLock<Ipv4MulticastGroups, BottomHalfDisabled>,
+    packet_sockets: SpinLock<Vec<Arc<PacketSocketBg<E>>>, BottomHalfDisabled>,
     sched_poll: E::ScheduleNextPoll,
 }
 
@@ -62,6 +72,8 @@ impl<E: Ext> IfaceCommon<E> {
             interface: SpinLock::new(PollableIface::new(interface)),
             used_ports: SpinLock::new(BTreeMap::new()),
             sockets: SpinLock::new(SocketTable::new()),
+            multicast_groups: SpinLock::new(Ipv4MulticastGroups::new()),
+            packet_sockets: SpinLock::new(Vec::new()),
             sched_poll,
         }
     }
@@ -82,6 +94,32 @@ impl<E: Ext> IfaceCommon<E> {
         self.flags
     }
 
+    /// Passes a packet sent by the iface to the packet filter.
+    ///
+    /// This method returns `None` if the packet is dropped. Otherwise, it returns `Some(None)` if
+    /// the packet is not translated, or `Some(Some(new_pkt))` if the packet is translated to
+    /// `new_pkt`.
+    pub(super) fn filter_outgoing<'p>(
+        &self,
+        pkt: &Packet<'p>,
+        iface_cx: &Context,
+    ) -> Option<Option<Packet<'p>>> {
+        let iface = PacketIface {
+            index: self.index,
+            ipv4_addr: iface_cx.ipv4_addr(),
+        };
+        let mut packet_info = PacketInfo::from_packet(iface, pkt);
+        if !run_hooks::<E>(&[Hook::Output, Hook::PostRouting], &mut packet_info) {
+            return None;
+        }
+
+        Some(packet_info.translate_packet(pkt))
+    }
+
+    pub(super) fn ether_addr(&self) -> Option<EthernetAddress> {
+        self.interface.lock().ether_addr()
+    }
+
     pub(super) fn ipv4_addr(&self) -> Option<Ipv4Address> {
         self.interface.lock().ipv4_addr()
     }
@@ -90,6 +128,58 @@ impl<E: Ext> IfaceCommon<E> {
         self.interface.lock().prefix_len()
     }
 
+    pub(super) fn add_ipv4_addr(&self, ipv4_cidr: Ipv4Cidr) -> bool {
+        self.interface.lock().add_ipv4_addr(ipv4_cidr)
+    }
+
+    pub(super) fn remove_ipv4_addr(&self, ipv4_addr: Ipv4Address) -> bool {
+        self.interface.lock().remove_ipv4_addr(ipv4_addr)
+    }
+
+    pub(super) fn ipv6_addrs(&self) -> Vec<Ipv6Cidr> {
+        self.interface.lock().ipv6_addrs()
+    }
+
+    pub(super) fn add_ipv6_addr(&self, ipv6_cidr: Ipv6Cidr) -> bool {
+        self.interface.lock().add_ipv6_addr(ipv6_cidr)
+    }
+
+    pub(super) fn remove_ipv6_addr(&self, ipv6_addr: Ipv6Address) -> bool {
+        self.interface.lock().remove_ipv6_addr(ipv6_addr)
+    }
+
+    pub(super) fn default_ipv4_gateway(&self) -> Option<Ipv4Address> {
+        self.interface.lock().default_ipv4_gateway()
+    }
+
+    pub(super) fn set_default_ipv4_route(&self, gateway: Ipv4Address) {
+        self.interface.lock().set_default_ipv4_route(gateway);
+    }
+
+    pub(super) fn remove_default_ipv4_route(&self) -> bool {
+        self.interface.lock().remove_default_ipv4_route()
+    }
+
+    pub(super) fn default_ipv6_gateway(&self) -> Option<Ipv6Address> {
+        self.interface.lock().default_ipv6_gateway()
+    }
+
+    pub(super) fn set_default_ipv6_route(&self, gateway: Ipv6Address) {
+        self.interface.lock().set_default_ipv6_route(gateway);
+    }
+
+    pub(super) fn remove_default_ipv6_route(&self) -> bool {
+        self.interface.lock().remove_default_ipv6_route()
+    }
+
+    pub(super) fn join_ipv4_multicast_group(&self, group_addr: Ipv4Address) -> bool {
+        self.multicast_groups.lock().join(group_addr)
+    }
+
+    pub(super) fn leave_ipv4_multicast_group(&self, group_addr: Ipv4Address) -> bool {
+        self.multicast_groups.lock().leave(group_addr)
+    }
+
     pub(super) fn sched_poll(&self) -> &E::ScheduleNextPoll {
         &self.sched_poll
     }
@@ -100,7 +190,7 @@ impl<E: Ext> IfaceCommon<E> {
 // FIXME: This allocator is specific to each network namespace.
 pub static INTERFACE_INDEX_ALLOCATOR: AtomicU32 = AtomicU32::new(1);
 
-// Lock order: `interface` -> `sockets`
+// Lock order: `interface` -> `sockets` -> `multicast_groups` -> `packet_sockets`
 impl<E: Ext> IfaceCommon<E> {
     /// Acquires the lock to the interface.
     pub(crate) fn interface(&self) -> SpinLockGuard<'_, PollableIface<E>, BottomHalfDisabled> {
@@ -120,13 +210,16 @@ impl<E: Ext> IfaceCommon<E> {
     pub(super) fn bind(
         &self,
         iface: Arc<dyn Iface<E>>,
+        addr: IpAddress,
         config: BindPortConfig,
     ) -> core::result::Result<BoundPort<E>, BindError> {
-        let (port, can_reuse) = self.bind_port(config)?;
+        let (port, reuse) = self.bind_port(config)?;
         Ok(BoundPort {
             iface,
+            addr,
             port,
-            can_reuse: AtomicBool::new(can_reuse),
+            can_reuse: AtomicBool::new(reuse.contains(PortReuse::ADDR)),
+            can_reuse_port: AtomicBool::new(reuse.contains(PortReuse::PORT)),
         })
     }
 
@@ -137,7 +230,7 @@ impl<E: Ext> IfaceCommon<E> {
     /// See <https://en.wikipedia.org/wiki/Ephemeral_port>.
     fn alloc_ephemeral_port(
         used_ports: &mut BTreeMap<u16, PortState>,
-        _can_reuse: bool,
+        _reuse: PortReuse,
     ) -> Option<u16> {
         for port in IP_LOCAL_PORT_START..=IP_LOCAL_PORT_END {
             if let Entry::Vacant(..) = used_ports.entry(port) {
@@ -145,20 +238,20 @@ impl<E: Ext> IfaceCommon<E> {
             }
         }
 
-        // FIXME: If `can_reuse` is `true`, we should also check all in-use ephemeral ports
+        // FIXME: If `reuse` is not empty, we should also check all in-use ephemeral ports
         // to see if any can be reused instead of directly returning `None`.
 
         None
     }
 
-    fn bind_port(&self, config: BindPortConfig) -> Result<(u16, bool), BindError> {
+    fn bind_port(&self, config: BindPortConfig) -> Result<(u16, PortReuse), BindError> {
         let mut used_ports = self.used_ports.lock();
-        let config_can_reuse = config.can_reuse();
+        let reuse = config.reuse();
 
         let port = if let Some(port) = config.port() {
             port
         } else {
-            match Self::alloc_ephemeral_port(&mut used_ports, config_can_reuse) {
+            match Self::alloc_ephemeral_port(&mut used_ports, reuse) {
                 Some(port) => port,
                 None => return Err(BindError::Exhausted),
             }
@@ -168,33 +261,33 @@ impl<E: Ext> IfaceCommon<E> {
             // FIXME: If the socket is not a backlog socket,
             // we should check whether there is a listening socket on the port.
             // If there is, the socket cannot be bound to that port.
-            let can_reuse = matches!(config, BindPortConfig::Backlog(_))
-                || (port_state.can_reuse() & config_can_reuse);
+            //
+            // FIXME: Linux only allows sockets with the same effective UID to share a port with
+            // `SO_REUSEPORT`. We don't check the UID here.
+            let can_reuse = matches!(config, BindPortConfig::Backlog(..))
+                || (port_state.can_reuse() && reuse.contains(PortReuse::ADDR))
+                || (port_state.can_reuse_port() && reuse.contains(PortReuse::PORT));
             if can_reuse {
                 port_state.nsocket += 1;
-                if config_can_reuse {
-                    port_state.nreuse += 1;
-                }
+                port_state.enable(reuse);
             } else {
                 return Err(BindError::InUse);
             }
         } else {
-            let port_state = PortState::new(config_can_reuse);
+            let port_state = PortState::new(reuse);
             used_ports.insert(port, port_state);
         };
 
-        Ok((port, config_can_reuse))
+        Ok((port, reuse))
     }
 
     /// Releases the port so that it can be used again.
-    fn release_port(&self, port: u16, can_reuse: bool) {
+    fn release_port(&self, port: u16, reuse: PortReuse) {
         let mut used_ports = self.used_ports.lock();
         if let Entry::Occupied(mut entry) = used_ports.entry(port) {
             let port_state = entry.get_mut();
             port_state.nsocket -= 1;
-            if can_reuse {
-                port_state.nreuse -= 1;
-            }
+            port_state.disable(reuse);
             if port_state.nsocket == 0 {
                 entry.remove_entry();
             }
@@ -208,9 +301,14 @@ impl<E: Ext> IfaceCommon<E> {
         sockets.insert_udp_socket(socket);
     }
 
+    pub(crate) fn register_raw_socket(&self, socket: Arc<RawIpSocketBg<E>>) {
+        let mut sockets = self.sockets.lock();
+        sockets.insert_raw_socket(socket);
+    }
+
     pub(crate) fn remove_tcp_listener(&self, socket: &Arc<TcpListenerBg<E>>) {
         let mut sockets = self.sockets.lock();
-        let removed = sockets.remove_listener(socket.listener_key());
+        let removed = sockets.remove_listener(socket);
         debug_assert!(removed.is_some());
     }
 
@@ -219,6 +317,157 @@ impl<E: Ext> IfaceCommon<E> {
         let removed = sockets.remove_udp_socket(socket);
         debug_assert!(removed.is_some());
     }
+
+    pub(crate) fn remove_raw_socket(&self, socket: &Arc<RawIpSocketBg<E>>) {
+        let mut sockets = self.sockets.lock();
+        let removed = sockets.remove_raw_socket(socket);
+        debug_assert!(removed.is_some());
+    }
+
+    pub(crate) fn register_packet_socket(&self, socket: Arc<PacketSocketBg<E>>) {
+        self.packet_sockets.lock().push(socket);
+    }
+
+    pub(crate) fn remove_packet_socket(&self, socket: &Arc<PacketSocketBg<E>>) {
+        let mut packet_sockets = self.packet_sockets.lock();
+        let index = packet_sockets
+            .iter()
+            .position(|packet_socket| Arc::ptr_eq(packet_socket, socket));
+        debug_assert!(index.is_some());
+        if let Some(index) = index {
+            packet_sockets.swap_remove(index);
+        }
+    }
+}
+
+impl<E: Ext> IfaceCommon<E> {
+    /// Delivers a frame that is received or sent on the iface to the packet sockets.
+    ///
+    /// If `medium` is [`Medium::Ip`], the frame is an IP packet, to which an Ethernet header with
+    /// zero addresses is added.
+    pub(super) fn deliver_frame(&self, data: &[u8], medium: Medium, frame_type: FrameType) {
+        let packet_sockets = self.packet_sockets.lock();
+        if packet_sockets.is_empty() {
+            return;
+        }
+
+        let frame = if medium == Medium::Ip {
+            let Some(frame) = ip_packet_to_frame(data) else {
+                return;
+            };
+            Cow::Owned(frame)
+        } else {
+            Cow::Borrowed(data)
+        };
+
+        deliver_frame_to(&packet_sockets, &frame, frame_type, None);
+    }
+
+    /// Transmits the frames sent by the packet sockets.
+    ///
+    /// Like Linux, the other packet sockets receive copies of the frames.
+    fn dispatch_frames<D: Device + ?Sized>(&self, device: &mut D, now: Instant) {
+        let medium = device.capabilities().medium;
+        let packet_sockets = self.packet_sockets.lock();
+
+        for socket in packet_sockets.iter() {
+            while socket.need_dispatch() {
+                let Some(tx_token) = device.transmit(now) else {
+                    return;
+                };
+                let Some(frame) = socket.dequeue_send() else {
+                    break;
+                };
+
+                deliver_frame_to(&packet_sockets, &frame, FrameType::Outgoing, Some(socket));
+
+                let data = if medium == Medium::Ip {
+                    let Some(packet) = frame_to_ip_packet(&frame) else {
+                        continue;
+                    };
+                    packet
+                } else {
+                    &frame
+                };
+                tx_token.consume(data.len(), |buffer| buffer.copy_from_slice(data));
+            }
+        }
+    }
+}
+
+/// Delivers a frame to the packet sockets, except the one that sends the frame.
+fn deliver_frame_to<E: Ext>(
+    packet_sockets: &[Arc<PacketSocketBg<E>>],
+    frame: &[u8],
+    frame_type: FrameType,
+    sender: Option<&Arc<PacketSocketBg<E>>>,
+) {
+    for socket in packet_sockets.iter() {
+        if sender.is_some_and(|sender| Arc::ptr_eq(sender, socket)) {
+            continue;
+        }
+        socket.process(frame, frame_type);
+    }
+}
+
+/// Adds an Ethernet header with zero addresses to an IP packet.
+fn ip_packet_to_frame(packet: &[u8]) -> Option<Vec<u8>> {
+    let ethertype = match IpVersion::of_packet(packet).ok()? {
+        IpVersion::Ipv4 => EthernetProtocol::Ipv4,
+        IpVersion::Ipv6 => EthernetProtocol::Ipv6,
+    };
+
+    let mut data = vec![0; ETHERNET_HEADER_LEN + packet.len()];
+    let mut frame = EthernetFrame::new_unchecked(data.as_mut_slice());
+    frame.set_ethertype(ethertype);
+    frame.payload_mut().copy_from_slice(packet);
+
+    Some(data)
+}
+
+/// Removes the Ethernet header from a frame that contains an IP packet.
+fn frame_to_ip_packet(frame: &[u8]) -> Option<&[u8]> {
+    let ethertype = EthernetFrame::new_checked(frame).ok()?.ethertype();
+    match ethertype {
+        EthernetProtocol::Ipv4 | EthernetProtocol::Ipv6 => Some(&frame[ETHERNET_HEADER_LEN..]),
+        _ => None,
+    }
+}
+
+/// A [`TxToken`] that delivers the outgoing frames to the packet sockets.
+pub(super) struct TapTxToken<'a, T, E: Ext> {
+    token: T,
+    common: &'a IfaceCommon<E>,
+    medium: Medium,
+}
+
+impl<'a, T, E: Ext> TapTxToken<'a, T, E> {
+    pub(super) fn new(token: T, common: &'a IfaceCommon<E>, medium: Medium) -> Self {
+        Self {
+            token,
+            common,
+            medium,
+        }
+    }
+}
+
+impl<T: TxToken, E: Ext> TxToken for TapTxToken<'_, T, E> {
+    fn consume<R, F>(self, len: usize, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        let Self {
+            token,
+            common,
+            medium,
+        } = self;
+
+        token.consume(len, |buffer| {
+            let res = f(buffer);
+            common.deliver_frame(buffer, medium, FrameType::Outgoing);
+            res
+        })
+    }
 }
 
 impl<E: Ext> IfaceCommon<E> {
@@ -234,17 +483,30 @@ impl<E: Ext> IfaceCommon<E> {
                 &'pkt [u8],
                 &'cx mut Context,
                 D::TxToken<'tx>,
-                Option<(Ipv4Packet<&'pkt [u8]>, D::TxToken<'tx>)>,
+                Option<(IpPacket<'pkt>, D::TxToken<'tx>)>,
             >,
         Q: FnMut(&Packet, &mut Context, D::TxToken<'_>),
     {
         let mut interface = self.interface();
-        interface.context_mut().now = get_network_timestamp();
+        let now = get_network_timestamp();
+        interface.context_mut().now = now;
+
+        // The frames sent by packet sockets are transmitted first, so that the frames looped back
+        // (e.g., by the loopback iface) can be received in the same poll.
+        self.dispatch_frames(device, now);
 
         let mut sockets = self.sockets.lock();
         let mut socket_actions = Vec::new();
 
-        let mut context = PollContext::new(interface.as_mut(), &sockets, &mut socket_actions);
+        let mut multicast_groups = self.multicast_groups.lock();
+
+        let mut context = PollContext::new(
+            interface.as_mut(),
+            self.index,
+            &sockets,
+            &mut multicast_groups,
+            &mut socket_actions,
+        );
         context.poll_ingress(device, &mut process_phy, &mut dispatch_phy);
         context.poll_egress(device, &mut dispatch_phy);
 
@@ -274,8 +536,10 @@ impl<E: Ext> IfaceCommon<E> {
 // FIXME: TCP and UDP ports are independent. Find a way to track the protocol here.
 pub struct BoundPort<E: Ext> {
     iface: Arc<dyn Iface<E>>,
+    addr: IpAddress,
     port: u16,
     can_reuse: AtomicBool,
+    can_reuse_port: AtomicBool,
 }
 
 impl<E: Ext> BoundPort<E> {
@@ -284,6 +548,11 @@ impl<E: Ext> BoundPort<E> {
         &self.iface
     }
 
+    /// Returns the IP address.
+    pub fn addr(&self) -> IpAddress {
+        self.addr
+    }
+
     /// Returns the port number.
     pub fn port(&self) -> u16 {
         self.port
@@ -291,39 +560,55 @@ impl<E: Ext> BoundPort<E> {
 
     /// Returns the bound endpoint.
     pub fn endpoint(&self) -> Option<IpEndpoint> {
-        let ip_addr = {
-            let ipv4_addr = self.iface().ipv4_addr()?;
-            IpAddress::Ipv4(ipv4_addr)
-        };
-        Some(IpEndpoint::new(ip_addr, self.port))
+        Some(IpEndpoint::new(self.addr, self.port))
     }
 
     /// Sets whether the port can be reused.
     pub fn set_can_reuse(&self, can_reuse: bool) {
+        self.set_reuse(&self.can_reuse, PortReuse::ADDR, can_reuse);
+    }
+
+    /// Returns whether the port can be shared by a group of sockets.
+    pub fn can_reuse_port(&self) -> bool {
+        self.can_reuse_port.load(Ordering::Relaxed)
+    }
+
+    /// Sets whether the port can be shared by a group of sockets.
+    pub fn set_can_reuse_port(&self, can_reuse_port: bool) {
+        self.set_reuse(&self.can_reuse_port, PortReuse::PORT, can_reuse_port);
+    }
+
+    fn set_reuse(&self, flag: &AtomicBool, reuse: PortReuse, enabled: bool) {
         let iface_common = self.iface.common();
         let mut used_ports = iface_common.used_ports.lock();
 
-        if self.can_reuse.load(Ordering::Relaxed) == can_reuse {
+        if flag.load(Ordering::Relaxed) == enabled {
             return;
         }
 
         if let Some(port_state) = used_ports.get_mut(&self.port) {
-            if can_reuse {
-                port_state.nreuse += 1;
+            if enabled {
+                port_state.enable(reuse);
             } else {
-                port_state.nreuse -= 1;
+                port_state.disable(reuse);
             }
         }
 
-        self.can_reuse.store(can_reuse, Ordering::Relaxed);
+        flag.store(enabled, Ordering::Relaxed);
+    }
+
+    fn reuse(&mut self) -> PortReuse {
+        let mut reuse = PortReuse::empty();
+        reuse.set(PortReuse::ADDR, *self.can_reuse.get_mut());
+        reuse.set(PortReuse::PORT, *self.can_reuse_port.get_mut());
+        reuse
     }
 }
 
 impl<E: Ext> Drop for BoundPort<E> {
     fn drop(&mut self) {
-        self.iface
-            .common()
-            .release_port(self.port, *self.can_reuse.get_mut());
+        let reuse = self.reuse();
+        self.iface.common().release_port(self.port, reuse);
     }
 }
 
@@ -331,17 +616,46 @@ struct PortState {
     nsocket: usize,
     /// The number of sockets that have enabled address reuse on this port.
     nreuse: usize,
+    /// The number of sockets that have enabled port reuse on this port.
+    nreuse_port: usize,
 }
 
 impl PortState {
-    pub(self) fn new(can_reuse: bool) -> Self {
-        let nreuse = if can_reuse { 1 } else { 0 };
-        Self { nsocket: 1, nreuse }
+    pub(self) fn new(reuse: PortReuse) -> Self {
+        let mut port_state = Self {
+            nsocket: 1,
+            nreuse: 0,
+            nreuse_port: 0,
+        };
+        port_state.enable(reuse);
+        port_state
+    }
+
+    pub(self) fn enable(&mut self, reuse: PortReuse) {
+        if reuse.contains(PortReuse::ADDR) {
+            self.nreuse += 1;
+        }
+        if reuse.contains(PortReuse::PORT) {
+            self.nreuse_port += 1;
+        }
+    }
+
+    pub(self) fn disable(&mut self, reuse: PortReuse) {
+        if reuse.contains(PortReuse::ADDR) {
+            self.nreuse -= 1;
+        }
+        if reuse.contains(PortReuse::PORT) {
+            self.nreuse_port -= 1;
+        }
     }
 
     pub(self) fn can_reuse(&self) -> bool {
         self.nsocket == self.nreuse
     }
+
+    pub(self) fn can_reuse_port(&self) -> bool {
+        self.nsocket == self.nreuse_port
+    }
 }
 
 /// Interface type.
@@ -371,6 +685,8 @@ pub enum InterfaceType {
     LOOPBACK = 772,
     /// Localtalk device
     LOCALTALK = 773,
+    /// Zero header length
+    NONE = 0xFFFE,
     // TODO: This enum is not exhaustive
 }
 
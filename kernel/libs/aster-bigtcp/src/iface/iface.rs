@@ -1,11 +1,11 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use alloc::sync::Arc;
+use alloc::{sync::Arc, vec::Vec};
 
-use smoltcp::wire::{Ipv4Address, Ipv4Cidr};
+use smoltcp::wire::{EthernetAddress, IpAddress, Ipv4Address, Ipv4Cidr, Ipv6Address, Ipv6Cidr};
 
 use super::{BoundPort, InterfaceFlags, InterfaceType, port::BindPortConfig};
-use crate::{errors::BindError, ext::Ext};
+use crate::{errors::BindError, ext::Ext, socket::NeedIfacePoll};
 
 /// A network interface.
 ///
@@ -18,6 +18,9 @@ pub trait Iface<E>: internal::IfaceInternal<E> + Send + Sync {
     fn poll(&self);
 
     /// Returns the maximum transmission unit.
+    ///
+    /// The MTU limits the size of the network-layer packets, so it does not include the size of
+    /// the link-layer header (e.g., the Ethernet header).
     fn mtu(&self) -> usize;
 }
 
@@ -25,7 +28,8 @@ impl<E: Ext> dyn Iface<E> {
     /// Binds a socket to the iface.
     ///
     /// After binding the socket to the iface, the iface will handle all packets to and from the
-    /// socket.
+    /// socket. `addr` is the local IP address of the socket, which should be one of the addresses
+    /// of the iface.
     ///
     /// If [`BindPortConfig::Ephemeral`] is specified, the iface will pick up an ephemeral port for
     /// the socket.
@@ -35,10 +39,11 @@ impl<E: Ext> dyn Iface<E> {
     /// <https://github.com/smoltcp-rs/smoltcp/issues/779>.
     pub fn bind(
         self: &Arc<Self>,
+        addr: IpAddress,
         config: BindPortConfig,
     ) -> core::result::Result<BoundPort<E>, BindError> {
         let common = self.common();
-        common.bind(self.clone(), config)
+        common.bind(self.clone(), addr, config)
     }
 
     /// Returns the interface index.
@@ -63,6 +68,13 @@ impl<E: Ext> dyn Iface<E> {
         self.common().flags()
     }
 
+    /// Gets the Ethernet address of the iface, if any.
+    ///
+    /// Ifaces that do not use Ethernet (e.g., the loopback iface) have no Ethernet addresses.
+    pub fn ether_addr(&self) -> Option<EthernetAddress> {
+        self.common().ether_addr()
+    }
+
     /// Gets the IPv4 address of the iface, if any.
     //
     // FIXME: One iface may have multiple IPv4 addresses.
@@ -78,6 +90,113 @@ impl<E: Ext> dyn Iface<E> {
         self.common().prefix_len()
     }
 
+    /// Adds an IPv4 address to the iface.
+    ///
+    /// This method returns `false` if the iface already has an IPv4 address or cannot hold more
+    /// addresses.
+    //
+    // FIXME: One iface may have multiple IPv4 addresses.
+    pub fn add_ipv4_addr(&self, ipv4_cidr: Ipv4Cidr) -> bool {
+        self.common().add_ipv4_addr(ipv4_cidr)
+    }
+
+    /// Removes an IPv4 address from the iface.
+    ///
+    /// This method returns `false` if the address does not exist.
+    pub fn remove_ipv4_addr(&self, ipv4_addr: Ipv4Address) -> bool {
+        self.common().remove_ipv4_addr(ipv4_addr)
+    }
+
+    /// Gets the IPv6 addresses of the iface.
+    pub fn ipv6_addrs(&self) -> Vec<Ipv6Cidr> {
+        self.common().ipv6_addrs()
+    }
+
+    /// Adds an IPv6 address to the iface.
+    ///
+    /// This method returns `false` if the address already exists or the iface cannot hold more
+    /// addresses.
+    pub fn add_ipv6_addr(&self, ipv6_cidr: Ipv6Cidr) -> bool {
+        self.common().add_ipv6_addr(ipv6_cidr)
+    }
+
+    /// Removes an IPv6 address from the iface.
+    ///
+    /// This method returns `false` if the address does not exist.
+    pub fn remove_ipv6_addr(&self, ipv6_addr: Ipv6Address) -> bool {
+        self.common().remove_ipv6_addr(ipv6_addr)
+    }
+
+    /// Gets the default gateway for IPv4 packets, if any.
+    pub fn default_ipv4_gateway(&self) -> Option<Ipv4Address> {
+        self.common().default_ipv4_gateway()
+    }
+
+    /// Sets the default gateway for IPv4 packets.
+    pub fn set_default_ipv4_route(&self, gateway: Ipv4Address) {
+        self.common().set_default_ipv4_route(gateway);
+    }
+
+    /// Removes the default gateway for IPv4 packets.
+    ///
+    /// This method returns `false` if there is no default gateway.
+    pub fn remove_default_ipv4_route(&self) -> bool {
+        self.common().remove_default_ipv4_route()
+    }
+
+    /// Gets the default gateway for IPv6 packets, if any.
+    pub fn default_ipv6_gateway(&self) -> Option<Ipv6Address> {
+        self.common().default_ipv6_gateway()
+    }
+
+    /// Sets the default gateway for IPv6 packets.
+    pub fn set_default_ipv6_route(&self, gateway: Ipv6Address) {
+        self.common().set_default_ipv6_route(gateway);
+    }
+
+    /// Removes the default gateway for IPv6 packets.
+    ///
+    /// This method returns `false` if there is no default gateway.
+    pub fn remove_default_ipv6_route(&self) -> bool {
+        self.common().remove_default_ipv6_route()
+    }
+
+    /// Joins an IPv4 multicast group.
+    ///
+    /// The joins are counted, so the iface leaves the group only after
+    /// [`Self::leave_ipv4_multicast_group`] has been called the same number of times.
+    ///
+    /// Polling the iface _may_ be required after this method succeeds.
+    pub fn join_ipv4_multicast_group(&self, group_addr: Ipv4Address) -> NeedIfacePoll {
+        if self.common().join_ipv4_multicast_group(group_addr) {
+            NeedIfacePoll::TRUE
+        } else {
+            NeedIfacePoll::FALSE
+        }
+    }
+
+    /// Leaves an IPv4 multicast group.
+    ///
+    /// Polling the iface _may_ be required after this method succeeds.
+    pub fn leave_ipv4_multicast_group(&self, group_addr: Ipv4Address) -> NeedIfacePoll {
+        if self.common().leave_ipv4_multicast_group(group_addr) {
+            NeedIfacePoll::TRUE
+        } else {
+            NeedIfacePoll::FALSE
+        }
+    }
+
+    /// Returns whether the IP address belongs to the iface.
+    pub fn has_ip_addr(&self, addr: IpAddress) -> bool {
+        match addr {
+            IpAddress::Ipv4(ipv4_addr) => self.ipv4_addr() == Some(ipv4_addr),
+            IpAddress::Ipv6(ipv6_addr) => self
+                .ipv6_addrs()
+                .iter()
+                .any(|ipv6_cidr| ipv6_cidr.address() == ipv6_addr),
+        }
+    }
+
     /// Gets the broadcast address of the iface, if any.
     pub fn broadcast_addr(&self) -> Option<Ipv4Address> {
         let cidr = {
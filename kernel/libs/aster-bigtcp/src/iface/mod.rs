@@ -3,6 +3,7 @@
 mod common;
 #[expect(clippy::module_inception)]
 mod iface;
+mod multicast;
 mod phy;
 mod poll;
 mod poll_iface;
@@ -14,5 +15,5 @@ pub use common::{BoundPort, InterfaceFlags, InterfaceType};
 pub use iface::Iface;
 pub use phy::{EtherIface, IpIface};
 pub(crate) use poll_iface::{PollKey, PollableIfaceMut};
-pub use port::BindPortConfig;
+pub use port::{BindPortConfig, PortReuse};
 pub use sched::ScheduleNextPoll;
@@ -0,0 +1,139 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::collections::{btree_map::BTreeMap, vec_deque::VecDeque};
+
+use smoltcp::wire::{IgmpRepr, IgmpVersion, Ipv4Address};
+
+/// The IPv4 multicast groups that an iface has joined.
+///
+/// The memberships are announced to the multicast routers using IGMP messages (see
+/// <https://datatracker.ietf.org/doc/html/rfc2236>).
+//
+// TODO: Send IGMPv3 reports when the routers send IGMPv3 queries. Currently, IGMPv2 reports are
+// sent, which IGMPv3 routers accept in their compatibility mode (see
+// <https://datatracker.ietf.org/doc/html/rfc3376#section-7.3.2>).
+pub(super) struct Ipv4MulticastGroups {
+    /// The number of joins of each group.
+    groups: BTreeMap<Ipv4Address, usize>,
+    /// The IGMP messages waiting to be sent.
+    pending_msgs: VecDeque<IgmpRepr>,
+}
+
+/// The all-hosts group, which every multicast-capable host joins implicitly.
+///
+/// The membership of this group is never reported.
+const ALL_HOSTS_GROUP: Ipv4Address = Ipv4Address::new(224, 0, 0, 1);
+
+/// The all-routers group, to which the leave messages are sent.
+const ALL_ROUTERS_GROUP: Ipv4Address = Ipv4Address::new(224, 0, 0, 2);
+
+impl Ipv4MulticastGroups {
+    pub(super) const fn new() -> Self {
+        Self {
+            groups: BTreeMap::new(),
+            pending_msgs: VecDeque::new(),
+        }
+    }
+
+    /// Joins a multicast group.
+    ///
+    /// This method returns whether an IGMP message is queued, in which case the iface should be
+    /// polled.
+    pub(super) fn join(&mut self, group_addr: Ipv4Address) -> bool {
+        let nr_joins = self.groups.entry(group_addr).or_insert(0);
+        *nr_joins += 1;
+
+        if *nr_joins > 1 || group_addr == ALL_HOSTS_GROUP {
+            return false;
+        }
+
+        self.pending_msgs.push_back(IgmpRepr::MembershipReport {
+            group_addr,
+            version: IgmpVersion::Version2,
+        });
+        true
+    }
+
+    /// Leaves a multicast group.
+    ///
+    /// This method returns whether an IGMP message is queued, in which case the iface should be
+    /// polled.
+    pub(super) fn leave(&mut self, group_addr: Ipv4Address) -> bool {
+        let Some(nr_joins) = self.groups.get_mut(&group_addr) else {
+            return false;
+        };
+        *nr_joins -= 1;
+
+        if *nr_joins > 0 {
+            return false;
+        }
+        self.groups.remove(&group_addr);
+
+        if group_addr == ALL_HOSTS_GROUP {
+            return false;
+        }
+
+        // Reporting the group is pointless now that it has been left.
+        self.pending_msgs.retain(|msg| match msg {
+            IgmpRepr::MembershipReport { group_addr: addr, .. } => *addr != group_addr,
+            _ => true,
+        });
+
+        self.pending_msgs.push_back(IgmpRepr::LeaveGroup { group_addr });
+        true
+    }
+
+    /// Returns whether the iface should receive the packets sent to the multicast group.
+    pub(super) fn contains(&self, group_addr: Ipv4Address) -> bool {
+        group_addr == ALL_HOSTS_GROUP || self.groups.contains_key(&group_addr)
+    }
+
+    /// Processes an incoming IGMP message.
+    ///
+    /// For a membership query, the reports of the queried groups are queued. Other messages are
+    /// ignored.
+    //
+    // TODO: Delay the reports by a random time up to the maximum response time, and suppress our
+    // reports if other hosts have reported the same groups.
+    pub(super) fn process(&mut self, igmp_repr: &IgmpRepr) {
+        let IgmpRepr::MembershipQuery {
+            group_addr: queried_addr,
+            version,
+            ..
+        } = igmp_repr
+        else {
+            return;
+        };
+
+        let is_general_query = queried_addr.is_unspecified();
+        for group_addr in self.groups.keys() {
+            let is_queried = is_general_query || group_addr == queried_addr;
+            if *group_addr == ALL_HOSTS_GROUP || !is_queried {
+                continue;
+            }
+
+            // IGMPv1 queries must be answered with IGMPv1 reports (see
+            // <https://datatracker.ietf.org/doc/html/rfc2236#section-4>).
+            let report = IgmpRepr::MembershipReport {
+                group_addr: *group_addr,
+                version: *version,
+            };
+            if !self.pending_msgs.contains(&report) {
+                self.pending_msgs.push_back(report);
+            }
+        }
+    }
+
+    /// Pops an IGMP message to send, together with its destination address.
+    pub(super) fn pop_pending_msg(&mut self) -> Option<(IgmpRepr, Ipv4Address)> {
+        let msg = self.pending_msgs.pop_front()?;
+
+        let dst_addr = match msg {
+            IgmpRepr::MembershipReport { group_addr, .. } => group_addr,
+            IgmpRepr::LeaveGroup { .. } => ALL_ROUTERS_GROUP,
+            IgmpRepr::MembershipQuery { .. } => unreachable!("queries are never sent"),
+        };
+
+        Some((msg, dst_addr))
+    }
+}
@@ -1,15 +1,21 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use alloc::{collections::btree_map::BTreeMap, string::String, sync::Arc};
+use alloc::{collections::btree_map::BTreeMap, string::String, sync::Arc, vec::Vec};
 
 use aster_softirq::BottomHalfDisabled;
 use ostd::sync::SpinLock;
 use smoltcp::{
-    iface::{Config, Context, packet::Packet},
-    phy::{Device, DeviceCapabilities, TxToken},
+    iface::{
+        Config, Context,
+        packet::{IpPayload, Packet},
+    },
+    phy::{Device, DeviceCapabilities, Medium, TxToken},
+    time::Duration,
     wire::{
         self, ArpOperation, ArpPacket, ArpRepr, EthernetAddress, EthernetFrame, EthernetProtocol,
-        EthernetRepr, IpAddress, Ipv4Address, Ipv4AddressExt, Ipv4Cidr, Ipv4Packet,
+        EthernetRepr, Icmpv6Packet, Icmpv6Repr, IpAddress, IpProtocol, IpRepr, Ipv4Address,
+        Ipv4AddressExt, Ipv4Cidr, Ipv4Packet, Ipv4Repr, Ipv6Address, Ipv6Cidr, Ipv6Packet,
+        Ipv6Repr, NdiscNeighborFlags, NdiscPrefixInfoFlags, NdiscRepr, RawHardwareAddress,
     },
 };
 
@@ -18,10 +24,12 @@ use crate::{
     ext::Ext,
     iface::{
         Iface, InterfaceFlags, ScheduleNextPoll,
-        common::{IfaceCommon, InterfaceType},
+        common::{IfaceCommon, InterfaceType, TapTxToken},
         iface::internal::IfaceInternal,
+        poll::IpPacket,
         time::get_network_timestamp,
     },
+    socket::FrameType,
 };
 
 pub struct EtherIface<D, E: Ext> {
@@ -29,14 +37,30 @@ pub struct EtherIface<D, E: Ext> {
     common: IfaceCommon<E>,
     ether_addr: EthernetAddress,
     arp_table: SpinLock<BTreeMap<Ipv4Address, EthernetAddress>, BottomHalfDisabled>,
+    neighbor_cache: SpinLock<BTreeMap<Ipv6Address, EthernetAddress>, BottomHalfDisabled>,
+    pending_router_configs: SpinLock<Vec<RouterConfig>, BottomHalfDisabled>,
+}
+
+/// The configuration learned from a router advertisement.
+struct RouterConfig {
+    router_addr: Ipv6Address,
+    is_default_router: bool,
+    /// The address generated by the stateless address autoconfiguration (SLAAC).
+    slaac_cidr: Option<Ipv6Cidr>,
+}
+
+/// A packet that resolves or advertises link-layer addresses.
+enum NeighborPacket {
+    Arp(ArpRepr),
+    Ndisc(EthernetRepr, Ipv6Repr, NdiscRepr<'static>),
 }
 
 impl<D: WithDevice, E: Ext> EtherIface<D, E> {
     pub fn new(
         driver: D,
         ether_addr: EthernetAddress,
-        ip_cidr: Ipv4Cidr,
-        gateway: Ipv4Address,
+        ip_cidr: Option<Ipv4Cidr>,
+        gateway: Option<Ipv4Address>,
         name: String,
         sched_poll: E::ScheduleNextPoll,
         flags: InterfaceFlags,
@@ -48,12 +72,21 @@ impl<D: WithDevice, E: Ext> EtherIface<D, E> {
             let mut interface = smoltcp::iface::Interface::new(config, device, now);
             interface.update_ip_addrs(|ip_addrs| {
                 debug_assert!(ip_addrs.is_empty());
-                ip_addrs.push(wire::IpCidr::Ipv4(ip_cidr)).unwrap();
+                if let Some(ip_cidr) = ip_cidr {
+                    ip_addrs.push(wire::IpCidr::Ipv4(ip_cidr)).unwrap();
+                }
+
+                let link_local_addr = eui64_addr(LINK_LOCAL_PREFIX, ether_addr);
+                ip_addrs
+                    .push(wire::IpCidr::Ipv6(Ipv6Cidr::new(link_local_addr, 64)))
+                    .unwrap();
             });
-            interface
-                .routes_mut()
-                .add_default_ipv4_route(gateway)
-                .unwrap();
+            if let Some(gateway) = gateway {
+                interface
+                    .routes_mut()
+                    .add_default_ipv4_route(gateway)
+                    .unwrap();
+            }
             interface
         });
 
@@ -64,6 +97,8 @@ impl<D: WithDevice, E: Ext> EtherIface<D, E> {
             common,
             ether_addr,
             arp_table: SpinLock::new(BTreeMap::new()),
+            neighbor_cache: SpinLock::new(BTreeMap::new()),
+            pending_router_configs: SpinLock::new(Vec::new()),
         })
     }
 }
@@ -88,11 +123,16 @@ where
             device.notify_poll_end();
             self.common.sched_poll().schedule_next_poll(next_poll);
         });
+
+        self.apply_router_configs();
     }
 
     fn mtu(&self) -> usize {
-        self.driver
-            .with(|device| device.capabilities().max_transmission_unit)
+        // The MTU of the device includes the Ethernet header, but the MTU of the iface does not.
+        let device_mtu = self
+            .driver
+            .with(|device| device.capabilities().max_transmission_unit);
+        device_mtu.saturating_sub(wire::ETHERNET_HEADER_LEN)
     }
 }
 
@@ -102,40 +142,74 @@ impl<D, E: Ext> EtherIface<D, E> {
         data: &'pkt [u8],
         iface_cx: &mut Context,
         tx_token: T,
-    ) -> Option<(Ipv4Packet<&'pkt [u8]>, T)> {
-        match self.parse_ip_or_process_arp(data, iface_cx) {
+    ) -> Option<(IpPacket<'pkt>, T)> {
+        self.deliver_incoming_frame(data);
+
+        match self.parse_ip_or_process_neighbor(data, iface_cx) {
             Ok(pkt) => Some((pkt, tx_token)),
-            Err(Some(arp)) => {
-                Self::emit_arp(&arp, tx_token);
+            Err(Some(neighbor_pkt)) => {
+                let tx_token = TapTxToken::new(tx_token, &self.common, Medium::Ethernet);
+                Self::emit_neighbor(&neighbor_pkt, &iface_cx.caps, tx_token);
                 None
             }
             Err(None) => None,
         }
     }
 
-    fn parse_ip_or_process_arp<'pkt>(
+    /// Delivers an incoming frame to the packet sockets.
+    ///
+    /// The frame is ignored if it is not sent to us.
+    fn deliver_incoming_frame(&self, data: &[u8]) {
+        let Ok(frame) = EthernetFrame::new_checked(data) else {
+            return;
+        };
+
+        let dst_addr = frame.dst_addr();
+        let frame_type = if dst_addr == self.ether_addr {
+            FrameType::Host
+        } else if dst_addr.is_broadcast() {
+            FrameType::Broadcast
+        } else if dst_addr.is_multicast() {
+            FrameType::Multicast
+        } else {
+            return;
+        };
+
+        self.common
+            .deliver_frame(data, Medium::Ethernet, frame_type);
+    }
+
+    fn parse_ip_or_process_neighbor<'pkt>(
         &self,
         data: &'pkt [u8],
         iface_cx: &mut Context,
-    ) -> Result<Ipv4Packet<&'pkt [u8]>, Option<ArpRepr>> {
+    ) -> Result<IpPacket<'pkt>, Option<NeighborPacket>> {
         // Parse the Ethernet header. Ignore the packet if the header is ill-formed.
         let frame = EthernetFrame::new_checked(data).map_err(|_| None)?;
         let repr = EthernetRepr::parse(&frame).map_err(|_| None)?;
 
-        // Ignore the Ethernet frame if it is not sent to us.
-        if !repr.dst_addr.is_broadcast() && repr.dst_addr != self.ether_addr {
+        // Ignore the Ethernet frame if it is not sent to us. Note that the broadcast address is
+        // also a multicast address, and IPv6 uses multicast addresses instead of broadcast.
+        if !repr.dst_addr.is_multicast() && repr.dst_addr != self.ether_addr {
             return Err(None);
         }
 
         // Ignore the Ethernet frame if the protocol is not supported.
         match repr.ethertype {
-            EthernetProtocol::Ipv4 => {
-                Ok(Ipv4Packet::new_checked(frame.payload()).map_err(|_| None)?)
+            EthernetProtocol::Ipv4 => Ok(IpPacket::Ipv4(
+                Ipv4Packet::new_checked(frame.payload()).map_err(|_| None)?,
+            )),
+            EthernetProtocol::Ipv6 => {
+                let pkt = Ipv6Packet::new_checked(frame.payload()).map_err(|_| None)?;
+                if let Some((ipv6_repr, ndisc_repr)) = parse_ndisc(&pkt, iface_cx) {
+                    return Err(self.process_ndisc(&repr, &ipv6_repr, &ndisc_repr, iface_cx));
+                }
+                Ok(IpPacket::Ipv6(pkt))
             }
             EthernetProtocol::Arp => {
                 let pkt = ArpPacket::new_checked(frame.payload()).map_err(|_| None)?;
                 let arp = ArpRepr::parse(&pkt).map_err(|_| None)?;
-                Err(self.process_arp(&arp, iface_cx))
+                Err(self.process_arp(&arp, iface_cx).map(NeighborPacket::Arp))
             }
             _ => Err(None),
         }
@@ -197,28 +271,209 @@ impl<D, E: Ext> EtherIface<D, E> {
         }
     }
 
+    fn process_ndisc(
+        &self,
+        ether_repr: &EthernetRepr,
+        ipv6_repr: &Ipv6Repr,
+        ndisc_repr: &NdiscRepr,
+        iface_cx: &mut Context,
+    ) -> Option<NeighborPacket> {
+        // Ignore the message if it may have been forwarded by a router. See
+        // <https://datatracker.ietf.org/doc/html/rfc4861#section-7.1.1>.
+        if ipv6_repr.hop_limit != 255 {
+            return None;
+        }
+
+        match *ndisc_repr {
+            NdiscRepr::NeighborSolicit {
+                target_addr,
+                lladdr,
+            } => {
+                // Ignore the message if we do not own the target address.
+                if !iface_cx.has_ip_addr(target_addr) {
+                    return None;
+                }
+
+                // TODO: Support the duplicate address detection (DAD), where the solicitations
+                // are sent from the unspecified address.
+                if ipv6_repr.src_addr.is_unspecified() {
+                    return None;
+                }
+
+                // Insert the mapping between the Ethernet address and the IP address of the
+                // soliciting node, since it is likely to receive our packets soon.
+                let source_ether = lladdr
+                    .and_then(parse_lladdr)
+                    .unwrap_or(ether_repr.src_addr);
+                if !source_ether.is_unicast() {
+                    return None;
+                }
+                self.neighbor_cache
+                    .lock()
+                    .insert(ipv6_repr.src_addr, source_ether);
+
+                Some(self.new_ndisc_packet(
+                    source_ether,
+                    target_addr,
+                    ipv6_repr.src_addr,
+                    NdiscRepr::NeighborAdvert {
+                        flags: NdiscNeighborFlags::SOLICITED | NdiscNeighborFlags::OVERRIDE,
+                        target_addr,
+                        lladdr: Some(RawHardwareAddress::from_bytes(self.ether_addr.as_bytes())),
+                    },
+                ))
+            }
+            NdiscRepr::NeighborAdvert {
+                target_addr,
+                lladdr,
+                ..
+            } => {
+                // Ignore the message if the link-layer address is absent or not unicast.
+                let target_ether = lladdr.and_then(parse_lladdr)?;
+                if !target_ether.is_unicast() {
+                    return None;
+                }
+
+                // Insert the mapping between the Ethernet address and the IP address.
+                //
+                // TODO: Remove the mapping if it expires.
+                self.neighbor_cache.lock().insert(target_addr, target_ether);
+
+                None
+            }
+            NdiscRepr::RouterAdvert {
+                router_lifetime,
+                lladdr,
+                prefix_info,
+                ..
+            } => {
+                // Ignore the message if the source address is not link-local. See
+                // <https://datatracker.ietf.org/doc/html/rfc4861#section-6.1.2>.
+                if !ipv6_repr.src_addr.is_unicast_link_local() {
+                    return None;
+                }
+
+                if let Some(router_ether) = lladdr.and_then(parse_lladdr)
+                    && router_ether.is_unicast()
+                {
+                    self.neighbor_cache
+                        .lock()
+                        .insert(ipv6_repr.src_addr, router_ether);
+                }
+
+                // Generate an address if the prefix is for the autoconfiguration. See
+                // <https://datatracker.ietf.org/doc/html/rfc4862#section-5.5.3>.
+                //
+                // TODO: Remove the generated address if its valid lifetime expires.
+                let slaac_cidr = prefix_info
+                    .filter(|info| {
+                        info.flags.contains(NdiscPrefixInfoFlags::ADDRCONF)
+                            && info.prefix_len == 64
+                            && !info.prefix.is_unicast_link_local()
+                    })
+                    .map(|info| Ipv6Cidr::new(eui64_addr(info.prefix, self.ether_addr), 64));
+
+                self.pending_router_configs.lock().push(RouterConfig {
+                    router_addr: ipv6_repr.src_addr,
+                    is_default_router: router_lifetime != Duration::ZERO,
+                    slaac_cidr,
+                });
+
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Applies the configurations learned from router advertisements.
+    ///
+    /// The configurations cannot be applied while processing the router advertisements, because
+    /// the interface is locked during polling.
+    fn apply_router_configs(&self) {
+        let router_configs = core::mem::take(&mut *self.pending_router_configs.lock());
+
+        for config in router_configs {
+            if let Some(slaac_cidr) = config.slaac_cidr {
+                self.common.add_ipv6_addr(slaac_cidr);
+            }
+            if config.is_default_router {
+                self.common.set_default_ipv6_route(config.router_addr);
+            }
+        }
+    }
+
+    fn new_ndisc_packet(
+        &self,
+        dst_ether: EthernetAddress,
+        src_addr: Ipv6Address,
+        dst_addr: Ipv6Address,
+        ndisc_repr: NdiscRepr<'static>,
+    ) -> NeighborPacket {
+        let ether_repr = EthernetRepr {
+            src_addr: self.ether_addr,
+            dst_addr: dst_ether,
+            ethertype: EthernetProtocol::Ipv6,
+        };
+        let ipv6_repr = Ipv6Repr {
+            src_addr,
+            dst_addr,
+            next_header: IpProtocol::Icmpv6,
+            payload_len: Icmpv6Repr::Ndisc(ndisc_repr).buffer_len(),
+            hop_limit: 255,
+        };
+
+        NeighborPacket::Ndisc(ether_repr, ipv6_repr, ndisc_repr)
+    }
+
     fn dispatch<T: TxToken>(&self, pkt: &Packet, iface_cx: &mut Context, tx_token: T) {
-        match self.resolve_ether_or_generate_arp(pkt, iface_cx) {
+        let Some(translated_pkt) = self.common.filter_outgoing(pkt, iface_cx) else {
+            return;
+        };
+        let pkt = translated_pkt.as_ref().unwrap_or(pkt);
+
+        let tx_token = TapTxToken::new(tx_token, &self.common, Medium::Ethernet);
+
+        match self.resolve_ether_or_generate_neighbor(pkt, iface_cx) {
             Ok(ether) => Self::emit_ip(&ether, pkt, &iface_cx.caps, tx_token),
-            Err(Some(arp)) => Self::emit_arp(&arp, tx_token),
+            Err(Some(neighbor_pkt)) => Self::emit_neighbor(&neighbor_pkt, &iface_cx.caps, tx_token),
             Err(None) => (),
         }
     }
 
-    fn resolve_ether_or_generate_arp(
+    fn resolve_ether_or_generate_neighbor(
         &self,
         pkt: &Packet,
         iface_cx: &mut Context,
+    ) -> Result<EthernetRepr, Option<NeighborPacket>> {
+        match pkt.ip_repr() {
+            IpRepr::Ipv4(ipv4_repr) => self
+                .resolve_ether_or_generate_arp(&ipv4_repr, iface_cx)
+                .map_err(|arp| arp.map(NeighborPacket::Arp)),
+            IpRepr::Ipv6(ipv6_repr) => self.resolve_ether_or_generate_ndisc(&ipv6_repr, iface_cx),
+        }
+    }
+
+    fn resolve_ether_or_generate_arp(
+        &self,
+        ipv4_repr: &Ipv4Repr,
+        iface_cx: &mut Context,
     ) -> Result<EthernetRepr, Option<ArpRepr>> {
-        // Resolve the next-hop IP address.
-        let next_hop_ip = match iface_cx.route(&pkt.ip_repr().dst_addr(), iface_cx.now()) {
-            Some(IpAddress::Ipv4(next_hop_ip)) => next_hop_ip,
-            None => return Err(None),
+        // Resolve the next-hop IP address. Multicast packets are sent directly on the link.
+        let next_hop_ip = if ipv4_repr.dst_addr.is_multicast() {
+            ipv4_repr.dst_addr
+        } else {
+            let dst_addr = IpAddress::Ipv4(ipv4_repr.dst_addr);
+            match iface_cx.route(&dst_addr, iface_cx.now()) {
+                Some(IpAddress::Ipv4(next_hop_ip)) => next_hop_ip,
+                _ => return Err(None),
+            }
         };
 
         // Resolve the next-hop Ethernet address.
         let next_hop_ether = if next_hop_ip.is_broadcast() {
             EthernetAddress::BROADCAST
+        } else if next_hop_ip.is_multicast() {
+            ipv4_multicast_ether_addr(next_hop_ip)
         } else if let Some(next_hop_ether) = self.arp_table.lock().get(&next_hop_ip) {
             *next_hop_ether
         } else {
@@ -241,6 +496,50 @@ impl<D, E: Ext> EtherIface<D, E> {
         })
     }
 
+    fn resolve_ether_or_generate_ndisc(
+        &self,
+        ipv6_repr: &Ipv6Repr,
+        iface_cx: &mut Context,
+    ) -> Result<EthernetRepr, Option<NeighborPacket>> {
+        // Resolve the next-hop IP address. Multicast packets are sent directly on the link.
+        let next_hop_ip = if ipv6_repr.dst_addr.is_multicast() {
+            ipv6_repr.dst_addr
+        } else {
+            let dst_addr = IpAddress::Ipv6(ipv6_repr.dst_addr);
+            match iface_cx.route(&dst_addr, iface_cx.now()) {
+                Some(IpAddress::Ipv6(next_hop_ip)) => next_hop_ip,
+                _ => return Err(None),
+            }
+        };
+
+        // Resolve the next-hop Ethernet address.
+        let next_hop_ether = if next_hop_ip.is_multicast() {
+            ipv6_multicast_ether_addr(next_hop_ip)
+        } else if let Some(next_hop_ether) = self.neighbor_cache.lock().get(&next_hop_ip) {
+            *next_hop_ether
+        } else {
+            // Like ARP, we drop the original packet and send a neighbor solicitation to the
+            // solicited-node multicast address instead. See
+            // <https://datatracker.ietf.org/doc/html/rfc4861#section-7.2.2>.
+            let solicited_node_addr = solicited_node_addr(next_hop_ip);
+            return Err(Some(self.new_ndisc_packet(
+                ipv6_multicast_ether_addr(solicited_node_addr),
+                ipv6_repr.src_addr,
+                solicited_node_addr,
+                NdiscRepr::NeighborSolicit {
+                    target_addr: next_hop_ip,
+                    lladdr: Some(RawHardwareAddress::from_bytes(self.ether_addr.as_bytes())),
+                },
+            )));
+        };
+
+        Ok(EthernetRepr {
+            src_addr: self.ether_addr,
+            dst_addr: next_hop_ether,
+            ethertype: EthernetProtocol::Ipv6,
+        })
+    }
+
     /// Consumes the token and emits an IP packet.
     fn emit_ip<T: TxToken>(
         ether_repr: &EthernetRepr,
@@ -265,6 +564,22 @@ impl<D, E: Ext> EtherIface<D, E> {
         );
     }
 
+    /// Consumes the token and emits an ARP packet or a Neighbor Discovery message.
+    fn emit_neighbor<T: TxToken>(
+        neighbor_pkt: &NeighborPacket,
+        caps: &DeviceCapabilities,
+        tx_token: T,
+    ) {
+        match neighbor_pkt {
+            NeighborPacket::Arp(arp_repr) => Self::emit_arp(arp_repr, tx_token),
+            NeighborPacket::Ndisc(ether_repr, ipv6_repr, ndisc_repr) => {
+                let icmp_repr = Icmpv6Repr::Ndisc(*ndisc_repr);
+                let ip_pkt = Packet::new_ipv6(*ipv6_repr, IpPayload::Icmpv6(icmp_repr));
+                Self::emit_ip(ether_repr, &ip_pkt, caps, tx_token);
+            }
+        }
+    }
+
     /// Consumes the token and emits an ARP packet.
     fn emit_arp<T: TxToken>(arp_repr: &ArpRepr, tx_token: T) {
         let ether_repr = match arp_repr {
@@ -289,3 +604,80 @@ impl<D, E: Ext> EtherIface<D, E> {
         });
     }
 }
+
+/// The prefix of the link-local addresses (`fe80::/64`).
+const LINK_LOCAL_PREFIX: Ipv6Address = Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 0);
+
+/// Parses the IPv6 packet as a Neighbor Discovery message.
+///
+/// This function returns `None` if the packet is not a well-formed Neighbor Discovery message.
+fn parse_ndisc<'pkt>(
+    pkt: &Ipv6Packet<&'pkt [u8]>,
+    iface_cx: &Context,
+) -> Option<(Ipv6Repr, NdiscRepr<'pkt>)> {
+    let ipv6_repr = Ipv6Repr::parse(pkt).ok()?;
+    if ipv6_repr.next_header != IpProtocol::Icmpv6 {
+        return None;
+    }
+
+    let icmp_pkt = Icmpv6Packet::new_checked(pkt.payload()).ok()?;
+    let icmp_repr = Icmpv6Repr::parse(
+        &ipv6_repr.src_addr,
+        &ipv6_repr.dst_addr,
+        &icmp_pkt,
+        &iface_cx.checksum_caps(),
+    )
+    .ok()?;
+
+    match icmp_repr {
+        Icmpv6Repr::Ndisc(ndisc_repr) => Some((ipv6_repr, ndisc_repr)),
+        _ => None,
+    }
+}
+
+/// Parses the link-layer address option of a Neighbor Discovery message.
+fn parse_lladdr(lladdr: RawHardwareAddress) -> Option<EthernetAddress> {
+    let bytes = lladdr.as_bytes();
+    (bytes.len() == 6).then(|| EthernetAddress::from_bytes(bytes))
+}
+
+/// Generates an IPv6 address from the 64-bit prefix and the modified EUI-64 interface identifier.
+///
+/// Reference: <https://datatracker.ietf.org/doc/html/rfc4291#appendix-A>.
+fn eui64_addr(prefix: Ipv6Address, ether_addr: EthernetAddress) -> Ipv6Address {
+    let mut octets = prefix.octets();
+    let ether_octets = ether_addr.as_bytes();
+
+    octets[8] = ether_octets[0] ^ 0x02;
+    octets[9..11].copy_from_slice(&ether_octets[1..3]);
+    octets[11] = 0xff;
+    octets[12] = 0xfe;
+    octets[13..16].copy_from_slice(&ether_octets[3..6]);
+
+    Ipv6Address::from(octets)
+}
+
+/// Returns the solicited-node multicast address of an IPv6 address.
+///
+/// Reference: <https://datatracker.ietf.org/doc/html/rfc4291#section-2.7.1>.
+fn solicited_node_addr(addr: Ipv6Address) -> Ipv6Address {
+    let mut octets = [0xff, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01, 0xff, 0, 0, 0];
+    octets[13..16].copy_from_slice(&addr.octets()[13..16]);
+    Ipv6Address::from(octets)
+}
+
+/// Returns the Ethernet address that an IPv4 multicast address is mapped to.
+///
+/// Reference: <https://datatracker.ietf.org/doc/html/rfc1112#section-6.4>.
+fn ipv4_multicast_ether_addr(addr: Ipv4Address) -> EthernetAddress {
+    let octets = addr.octets();
+    EthernetAddress([0x01, 0x00, 0x5e, octets[1] & 0x7f, octets[2], octets[3]])
+}
+
+/// Returns the Ethernet address that an IPv6 multicast address is mapped to.
+///
+/// Reference: <https://datatracker.ietf.org/doc/html/rfc2464#section-7>.
+fn ipv6_multicast_ether_addr(addr: Ipv6Address) -> EthernetAddress {
+    let octets = addr.octets();
+    EthernetAddress([0x33, 0x33, octets[12], octets[13], octets[14], octets[15]])
+}
@@ -4,8 +4,8 @@ use alloc::{string::String, sync::Arc};
 
 use smoltcp::{
     iface::Config,
-    phy::{Device, TxToken},
-    wire::{self, Ipv4Cidr, Ipv4Packet},
+    phy::{Device, Medium, TxToken},
+    wire::{self, IpVersion, Ipv4Cidr, Ipv4Packet, Ipv6Packet},
 };
 
 use crate::{
@@ -13,10 +13,12 @@ use crate::{
     ext::Ext,
     iface::{
         Iface, ScheduleNextPoll,
-        common::{IfaceCommon, InterfaceFlags, InterfaceType},
+        common::{IfaceCommon, InterfaceFlags, InterfaceType, TapTxToken},
         iface::internal::IfaceInternal,
+        poll::IpPacket,
         time::get_network_timestamp,
     },
+    socket::FrameType,
 };
 
 pub struct IpIface<D, E: Ext> {
@@ -27,7 +29,7 @@ pub struct IpIface<D, E: Ext> {
 impl<D: WithDevice, E: Ext> IpIface<D, E> {
     pub fn new(
         driver: D,
-        ip_cidr: Ipv4Cidr,
+        ip_cidr: Option<Ipv4Cidr>,
         name: String,
         sched_poll: E::ScheduleNextPoll,
         type_: InterfaceType,
@@ -38,10 +40,12 @@ impl<D: WithDevice, E: Ext> IpIface<D, E> {
             let now = get_network_timestamp();
 
             let mut interface = smoltcp::iface::Interface::new(config, device, now);
-            interface.update_ip_addrs(|ip_addrs| {
-                debug_assert!(ip_addrs.is_empty());
-                ip_addrs.push(wire::IpCidr::Ipv4(ip_cidr)).unwrap();
-            });
+            if let Some(ip_cidr) = ip_cidr {
+                interface.update_ip_addrs(|ip_addrs| {
+                    debug_assert!(ip_addrs.is_empty());
+                    ip_addrs.push(wire::IpCidr::Ipv4(ip_cidr)).unwrap();
+                });
+            }
             interface
         });
 
@@ -62,8 +66,22 @@ impl<D: WithDevice + 'static, E: Ext> Iface<E> for IpIface<D, E> {
         self.driver.with(|device| {
             let next_poll = self.common.poll(
                 device,
-                |data, _iface_cx, tx_token| Some((Ipv4Packet::new_checked(data).ok()?, tx_token)),
+                |data, _iface_cx, tx_token| {
+                    self.common.deliver_frame(data, Medium::Ip, FrameType::Host);
+
+                    let pkt = match IpVersion::of_packet(data).ok()? {
+                        IpVersion::Ipv4 => IpPacket::Ipv4(Ipv4Packet::new_checked(data).ok()?),
+                        IpVersion::Ipv6 => IpPacket::Ipv6(Ipv6Packet::new_checked(data).ok()?),
+                    };
+                    Some((pkt, tx_token))
+                },
                 |pkt, iface_cx, tx_token| {
+                    let Some(translated_pkt) = self.common.filter_outgoing(pkt, iface_cx) else {
+                        return;
+                    };
+                    let pkt = translated_pkt.as_ref().unwrap_or(pkt);
+
+                    let tx_token = TapTxToken::new(tx_token, &self.common, Medium::Ip);
                     let ip_repr = pkt.ip_repr();
                     tx_token.consume(ip_repr.buffer_len(), |buffer| {
                         ip_repr.emit(&mut buffer[..], &iface_cx.checksum_caps());
@@ -7,24 +7,28 @@ use smoltcp::{
         Context,
         packet::{IpPayload, Packet, icmp_reply_payload_len},
     },
-    phy::{ChecksumCapabilities, Device, RxToken, TxToken},
+    phy::{ChecksumCapabilities, Device, DeviceCapabilities, RxToken, TxToken},
     wire::{
-        IPV4_HEADER_LEN, IPV4_MIN_MTU, Icmpv4DstUnreachable, Icmpv4Repr, IpAddress, IpProtocol,
-        IpRepr, Ipv4Address, Ipv4Packet, Ipv4Repr, TcpControl, TcpPacket, TcpRepr, UdpPacket,
-        UdpRepr,
+        IPV4_HEADER_LEN, IPV4_MIN_MTU, IPV6_HEADER_LEN, IPV6_MIN_MTU, Icmpv4DstUnreachable,
+        Icmpv4Packet, Icmpv4Repr, Icmpv6DstUnreachable, Icmpv6Packet, Icmpv6Repr, IgmpPacket,
+        IgmpRepr, IpAddress, IpEndpoint, IpProtocol, IpRepr, Ipv4Address, Ipv4Packet, Ipv4Repr,
+        Ipv6Address, Ipv6Packet, Ipv6Repr, TcpControl, TcpPacket, TcpRepr, UdpPacket, UdpRepr,
     },
 };
 
-use super::poll_iface::PollableIfaceMut;
+use super::{multicast::Ipv4MulticastGroups, poll_iface::PollableIfaceMut};
 use crate::{
     ext::Ext,
+    filter::{Hook, PacketIface, PacketInfo, run_hooks},
     socket::{TcpConnectionBg, TcpProcessResult},
     socket_table::{ConnectionKey, ListenerKey, SocketTable},
 };
 
 pub(super) struct PollContext<'a, E: Ext> {
     iface: PollableIfaceMut<'a, E>,
+    ifindex: u32,
     sockets: &'a SocketTable<E>,
+    multicast_groups: &'a mut Ipv4MulticastGroups,
     actions: &'a mut Vec<SocketTableAction<E>>,
 }
 
@@ -40,17 +44,27 @@ pub(super) enum SocketTableAction<E: Ext> {
 impl<'a, E: Ext> PollContext<'a, E> {
     pub(super) fn new(
         iface: PollableIfaceMut<'a, E>,
+        ifindex: u32,
         sockets: &'a SocketTable<E>,
+        multicast_groups: &'a mut Ipv4MulticastGroups,
         actions: &'a mut Vec<SocketTableAction<E>>,
     ) -> Self {
         Self {
             iface,
+            ifindex,
             sockets,
+            multicast_groups,
             actions,
         }
     }
 }
 
+/// An IP packet that is received from the physical layer.
+pub(super) enum IpPacket<'pkt> {
+    Ipv4(Ipv4Packet<&'pkt [u8]>),
+    Ipv6(Ipv6Packet<&'pkt [u8]>),
+}
+
 // This works around <https://github.com/rust-lang/rust/issues/49601>.
 // See the issue above for details.
 pub(super) trait FnHelper<A, B, C, O>: FnMut(A, B, C) -> O {}
@@ -68,7 +82,7 @@ impl<E: Ext> PollContext<'_, E> {
                 &'pkt [u8],
                 &'cx mut Context,
                 D::TxToken<'tx>,
-                Option<(Ipv4Packet<&'pkt [u8]>, D::TxToken<'tx>)>,
+                Option<(IpPacket<'pkt>, D::TxToken<'tx>)>,
             >,
         Q: FnMut(&Packet, &mut Context, D::TxToken<'_>),
     {
@@ -79,7 +93,11 @@ impl<E: Ext> PollContext<'_, E> {
                     return;
                 };
 
-                let Some(reply) = self.parse_and_process_ipv4(pkt) else {
+                let reply = match pkt {
+                    IpPacket::Ipv4(pkt) => self.parse_and_process_ipv4(pkt),
+                    IpPacket::Ipv6(pkt) => self.parse_and_process_ipv6(pkt),
+                };
+                let Some(reply) = reply else {
                     return;
                 };
 
@@ -95,7 +113,20 @@ impl<E: Ext> PollContext<'_, E> {
         // Parse the IP header. Ignore the packet if the header is ill-formed.
         let repr = Ipv4Repr::parse(&pkt, &self.iface.context().checksum_caps()).ok()?;
 
-        if !repr.dst_addr.is_broadcast() && !self.is_unicast_local(IpAddress::Ipv4(repr.dst_addr)) {
+        let mut packet_info =
+            PacketInfo::parse(self.packet_iface(), &IpRepr::Ipv4(repr), pkt.payload());
+        if !run_hooks::<E>(&[Hook::PreRouting], &mut packet_info) {
+            return None;
+        }
+
+        // The destination address may have been translated by the packet filter.
+        let IpAddress::Ipv4(dst_addr) = packet_info.dst_addr else {
+            return None;
+        };
+        if !dst_addr.is_broadcast()
+            && !self.is_unicast_local(IpAddress::Ipv4(dst_addr))
+            && !self.multicast_groups.contains(dst_addr)
+        {
             return self.generate_icmp_unreachable(
                 &IpRepr::Ipv4(repr),
                 pkt.payload(),
@@ -103,23 +134,204 @@ impl<E: Ext> PollContext<'_, E> {
             );
         }
 
+        // Ignore the multicast packets sent by ourselves. They have been looped back when they
+        // were sent if `IP_MULTICAST_LOOP` is enabled.
+        if repr.dst_addr.is_multicast() && self.is_unicast_local(IpAddress::Ipv4(repr.src_addr)) {
+            return None;
+        }
+
+        if !run_hooks::<E>(&[Hook::Input], &mut packet_info) {
+            return None;
+        }
+
+        // Like Linux, raw sockets receive copies of the packets before the packets are processed
+        // by the protocols.
+        let ip_packet = &pkt.as_ref()[..pkt.total_len() as usize];
+        for socket in self.sockets.raw_socket_iter() {
+            socket.process(&repr, ip_packet, pkt.payload());
+        }
+
         let checksum_caps = self.iface.context().checksum_caps();
         match repr.next_header {
-            IpProtocol::Tcp => {
-                self.parse_and_process_tcp(&IpRepr::Ipv4(repr), pkt.payload(), &checksum_caps)
+            IpProtocol::Tcp => self.parse_and_process_tcp(
+                &IpRepr::Ipv4(repr),
+                pkt.payload(),
+                &checksum_caps,
+                Some(&packet_info),
+            ),
+            IpProtocol::Udp => self.parse_and_process_udp(
+                &IpRepr::Ipv4(repr),
+                pkt.payload(),
+                &checksum_caps,
+                Some(&packet_info),
+            ),
+            IpProtocol::Icmp => self.parse_and_process_icmpv4(&repr, pkt.payload(), &checksum_caps),
+            IpProtocol::Igmp => self.parse_and_process_igmp(pkt.payload()),
+            _ => None,
+        }
+    }
+
+    fn parse_and_process_icmpv4<'pkt>(
+        &mut self,
+        ipv4_repr: &Ipv4Repr,
+        ip_payload: &'pkt [u8],
+        checksum_caps: &ChecksumCapabilities,
+    ) -> Option<Packet<'pkt>> {
+        // Parse the ICMP message. Ignore the packet if the message is ill-formed.
+        let icmp_pkt = Icmpv4Packet::new_checked(ip_payload).ok()?;
+        let icmp_repr = Icmpv4Repr::parse(&icmp_pkt, checksum_caps).ok()?;
+
+        match icmp_repr {
+            // Echo requests sent to broadcast or multicast addresses are ignored, as Linux does by
+            // default.
+            Icmpv4Repr::EchoRequest {
+                ident,
+                seq_no,
+                data,
+            } if !ipv4_repr.dst_addr.is_broadcast() && !ipv4_repr.dst_addr.is_multicast() => {
+                let reply_repr = Icmpv4Repr::EchoReply {
+                    ident,
+                    seq_no,
+                    data,
+                };
+                Some(Packet::new_ipv4(
+                    Ipv4Repr {
+                        src_addr: ipv4_repr.dst_addr,
+                        dst_addr: ipv4_repr.src_addr,
+                        next_header: IpProtocol::Icmp,
+                        payload_len: reply_repr.buffer_len(),
+                        hop_limit: 64,
+                    },
+                    IpPayload::Icmpv4(reply_repr),
+                ))
             }
-            IpProtocol::Udp => {
-                self.parse_and_process_udp(&IpRepr::Ipv4(repr), pkt.payload(), &checksum_caps)
+            // Echo replies are delivered to the ping socket with the same identifier.
+            Icmpv4Repr::EchoReply { ident, .. } => {
+                for socket in self.sockets.raw_socket_iter() {
+                    if socket.process_echo_reply(ipv4_repr, ident, ip_payload) {
+                        break;
+                    }
+                }
+                None
             }
+            // TODO: Deliver ICMP error messages to the sockets that sent the original packets.
             _ => None,
         }
     }
 
+    fn parse_and_process_igmp<'pkt>(&mut self, ip_payload: &'pkt [u8]) -> Option<Packet<'pkt>> {
+        // Parse the IGMP message. Ignore the packet if the message is ill-formed.
+        let igmp_pkt = IgmpPacket::new_checked(ip_payload).ok()?;
+        let igmp_repr = IgmpRepr::parse(&igmp_pkt).ok()?;
+
+        // The reports to the queries are queued and sent when polling the egress.
+        self.multicast_groups.process(&igmp_repr);
+
+        None
+    }
+
+    fn parse_and_process_ipv6<'pkt>(
+        &mut self,
+        pkt: Ipv6Packet<&'pkt [u8]>,
+    ) -> Option<Packet<'pkt>> {
+        // Parse the IP header. Ignore the packet if the header is ill-formed.
+        let repr = Ipv6Repr::parse(&pkt).ok()?;
+
+        let mut packet_info =
+            PacketInfo::parse(self.packet_iface(), &IpRepr::Ipv6(repr), pkt.payload());
+        if !run_hooks::<E>(&[Hook::PreRouting], &mut packet_info) {
+            return None;
+        }
+
+        // Ignore the packet if it is not sent to us. Unlike IPv4, no ICMP error is generated here
+        // because a host that does not forward packets silently discards them.
+        let IpAddress::Ipv6(dst_addr) = packet_info.dst_addr else {
+            return None;
+        };
+        if !self.is_multicast_local(dst_addr) && !self.is_unicast_local(IpAddress::Ipv6(dst_addr)) {
+            return None;
+        }
+
+        if !run_hooks::<E>(&[Hook::Input], &mut packet_info) {
+            return None;
+        }
+
+        // TODO: Support IPv6 extension headers.
+        let checksum_caps = self.iface.context().checksum_caps();
+        match repr.next_header {
+            IpProtocol::Tcp => self.parse_and_process_tcp(
+                &IpRepr::Ipv6(repr),
+                pkt.payload(),
+                &checksum_caps,
+                Some(&packet_info),
+            ),
+            IpProtocol::Udp => self.parse_and_process_udp(
+                &IpRepr::Ipv6(repr),
+                pkt.payload(),
+                &checksum_caps,
+                Some(&packet_info),
+            ),
+            IpProtocol::Icmpv6 => {
+                Self::parse_and_process_icmpv6(&repr, pkt.payload(), &checksum_caps)
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_and_process_icmpv6<'pkt>(
+        ipv6_repr: &Ipv6Repr,
+        ip_payload: &'pkt [u8],
+        checksum_caps: &ChecksumCapabilities,
+    ) -> Option<Packet<'pkt>> {
+        // Parse the ICMPv6 message. Ignore the packet if the message is ill-formed.
+        let icmp_pkt = Icmpv6Packet::new_checked(ip_payload).ok()?;
+        let icmp_repr = Icmpv6Repr::parse(
+            &ipv6_repr.src_addr,
+            &ipv6_repr.dst_addr,
+            &icmp_pkt,
+            checksum_caps,
+        )
+        .ok()?;
+
+        match icmp_repr {
+            // Echo requests sent to multicast addresses are ignored, as Linux does by default.
+            Icmpv6Repr::EchoRequest {
+                ident,
+                seq_no,
+                data,
+            } if !ipv6_repr.dst_addr.is_multicast() => {
+                let reply_repr = Icmpv6Repr::EchoReply {
+                    ident,
+                    seq_no,
+                    data,
+                };
+                Some(Packet::new_ipv6(
+                    Ipv6Repr {
+                        src_addr: ipv6_repr.dst_addr,
+                        dst_addr: ipv6_repr.src_addr,
+                        next_header: IpProtocol::Icmpv6,
+                        payload_len: reply_repr.buffer_len(),
+                        hop_limit: 64,
+                    },
+                    IpPayload::Icmpv6(reply_repr),
+                ))
+            }
+            // Neighbor Discovery messages are handled by the Ethernet iface, since they are used
+            // to resolve link-layer addresses. Other messages are not supported yet.
+            _ => None,
+        }
+    }
+
+    /// Parses and processes a TCP packet.
+    ///
+    /// If `translation` is not `None`, the packet is translated by the packet filter after it is
+    /// parsed.
     fn parse_and_process_tcp<'pkt>(
         &mut self,
         ip_repr: &IpRepr,
         ip_payload: &'pkt [u8],
         checksum_caps: &ChecksumCapabilities,
+        translation: Option<&PacketInfo>,
     ) -> Option<Packet<'pkt>> {
         // TCP connections can only be established between unicast addresses. Ignore the packet if
         // this is not the case. See
@@ -137,8 +349,12 @@ impl<E: Ext> PollContext<'_, E> {
             checksum_caps,
         )
         .ok()?;
+        let (ip_repr, tcp_repr) = match translation {
+            Some(packet_info) => packet_info.translate_tcp(ip_repr, &tcp_repr),
+            None => (ip_repr.clone(), tcp_repr),
+        };
 
-        self.process_tcp_until_outgoing(ip_repr, &tcp_repr)
+        self.process_tcp_until_outgoing(&ip_repr, &tcp_repr)
             .map(|(ip_repr, tcp_repr)| Packet::new(ip_repr, IpPayload::Tcp(tcp_repr)))
     }
 
@@ -154,7 +370,10 @@ impl<E: Ext> PollContext<'_, E> {
                 return Some((ip_repr, tcp_repr));
             }
 
-            let (new_ip_repr, new_tcp_repr) = self.process_tcp(&ip_repr, &tcp_repr)?;
+            let (looped_ip_repr, looped_tcp_repr) =
+                self.filter_looped_back_tcp(&ip_repr, &tcp_repr)?;
+
+            let (new_ip_repr, new_tcp_repr) = self.process_tcp(&looped_ip_repr, &looped_tcp_repr)?;
             ip_repr = new_ip_repr;
             tcp_repr = new_tcp_repr;
         }
@@ -221,7 +440,7 @@ impl<E: Ext> PollContext<'_, E> {
         // Process packets that request to create new connections second.
         if tcp_repr.control == TcpControl::Syn && tcp_repr.ack_number.is_none() {
             let listener_key = ListenerKey::new(ip_repr.dst_addr(), tcp_repr.dst_port);
-            if let Some(listener) = self.sockets.lookup_listener(&listener_key) {
+            if let Some(listener) = self.sockets.lookup_listener(&listener_key, &connection_key) {
                 let (processed, new_tcp_conn) =
                     listener.process(&mut self.iface, ip_repr, tcp_repr);
 
@@ -248,11 +467,16 @@ impl<E: Ext> PollContext<'_, E> {
         Some(smoltcp::socket::tcp::Socket::rst_reply(ip_repr, tcp_repr))
     }
 
+    /// Parses and processes a UDP packet.
+    ///
+    /// If `translation` is not `None`, the packet is translated by the packet filter after it is
+    /// parsed.
     fn parse_and_process_udp<'pkt>(
         &mut self,
         ip_repr: &IpRepr,
         ip_payload: &'pkt [u8],
         checksum_caps: &ChecksumCapabilities,
+        translation: Option<&PacketInfo>,
     ) -> Option<Packet<'pkt>> {
         // Parse the UDP header. Ignore the packet if the header is ill-formed.
         let udp_pkt = UdpPacket::new_checked(ip_payload).ok()?;
@@ -263,8 +487,13 @@ impl<E: Ext> PollContext<'_, E> {
             checksum_caps,
         )
         .ok()?;
+        let (translated_ip_repr, udp_repr) = match translation {
+            Some(packet_info) => packet_info.translate_udp(ip_repr, &udp_repr),
+            None => (ip_repr.clone(), udp_repr),
+        };
 
-        if !self.process_udp(ip_repr, &udp_repr, udp_pkt.payload()) {
+        if !self.process_udp(&translated_ip_repr, &udp_repr, udp_pkt.payload()) {
+            // The ICMP message contains the packet before it is translated.
             return self.generate_icmp_unreachable(
                 ip_repr,
                 ip_payload,
@@ -276,6 +505,21 @@ impl<E: Ext> PollContext<'_, E> {
     }
 
     fn process_udp(&mut self, ip_repr: &IpRepr, udp_repr: &UdpRepr, udp_payload: &[u8]) -> bool {
+        if ip_repr.dst_addr().is_unicast() {
+            let flow = (
+                IpEndpoint::new(ip_repr.dst_addr(), udp_repr.dst_port),
+                IpEndpoint::new(ip_repr.src_addr(), udp_repr.src_port),
+            );
+            // If the selected socket does not accept the packet (e.g., because it is connected to
+            // another remote endpoint), fall back to trying all the sockets below.
+            let socket = self.sockets.lookup_reuse_port_udp_socket(&flow);
+            if socket.is_some_and(|socket| {
+                socket.process(self.iface.context_mut(), ip_repr, udp_repr, udp_payload)
+            }) {
+                return true;
+            }
+        }
+
         let mut processed = false;
 
         for socket in self.sockets.udp_socket_iter() {
@@ -312,28 +556,56 @@ impl<E: Ext> PollContext<'_, E> {
             return None;
         }
 
-        let IpRepr::Ipv4(ipv4_repr) = ip_repr;
+        let ipv6_repr = match ip_repr {
+            IpRepr::Ipv4(ipv4_repr) => {
+                let reply_len =
+                    icmp_reply_payload_len(ip_payload.len(), IPV4_MIN_MTU, IPV4_HEADER_LEN);
+                let icmp_repr = Icmpv4Repr::DstUnreachable {
+                    reason,
+                    header: *ipv4_repr,
+                    data: &ip_payload[..reply_len],
+                };
+
+                return Some(Packet::new_ipv4(
+                    Ipv4Repr {
+                        src_addr: self
+                            .iface
+                            .context()
+                            .ipv4_addr()
+                            .unwrap_or(Ipv4Address::UNSPECIFIED),
+                        dst_addr: ipv4_repr.src_addr,
+                        next_header: IpProtocol::Icmp,
+                        payload_len: icmp_repr.buffer_len(),
+                        hop_limit: 64,
+                    },
+                    IpPayload::Icmpv4(icmp_repr),
+                ));
+            }
+            IpRepr::Ipv6(ipv6_repr) => ipv6_repr,
+        };
 
-        let reply_len = icmp_reply_payload_len(ip_payload.len(), IPV4_MIN_MTU, IPV4_HEADER_LEN);
-        let icmp_repr = Icmpv4Repr::DstUnreachable {
+        let reason = match reason {
+            Icmpv4DstUnreachable::PortUnreachable => Icmpv6DstUnreachable::PortUnreachable,
+            _ => Icmpv6DstUnreachable::AddrUnreachable,
+        };
+        let reply_len = icmp_reply_payload_len(ip_payload.len(), IPV6_MIN_MTU, IPV6_HEADER_LEN);
+        let icmp_repr = Icmpv6Repr::DstUnreachable {
             reason,
-            header: *ipv4_repr,
+            header: *ipv6_repr,
             data: &ip_payload[..reply_len],
         };
 
-        Some(Packet::new_ipv4(
-            Ipv4Repr {
-                src_addr: self
-                    .iface
-                    .context()
-                    .ipv4_addr()
-                    .unwrap_or(Ipv4Address::UNSPECIFIED),
-                dst_addr: ipv4_repr.src_addr,
-                next_header: IpProtocol::Icmp,
+        Some(Packet::new_ipv6(
+            Ipv6Repr {
+                // The destination address is local because IPv6 packets sent to other hosts have
+                // been discarded.
+                src_addr: ipv6_repr.dst_addr,
+                dst_addr: ipv6_repr.src_addr,
+                next_header: IpProtocol::Icmpv6,
                 payload_len: icmp_repr.buffer_len(),
                 hop_limit: 64,
             },
-            IpPayload::Icmpv4(icmp_repr),
+            IpPayload::Icmpv6(icmp_repr),
         ))
     }
 
@@ -348,8 +620,72 @@ impl<E: Ext> PollContext<'_, E> {
                 .context()
                 .ipv4_addr()
                 .is_some_and(|addr| addr == dst_addr),
+            IpAddress::Ipv6(dst_addr) => {
+                !dst_addr.is_multicast() && self.iface.context().has_ip_addr(dst_addr)
+            }
         }
     }
+
+    /// Passes a packet from the local host to the local host to the packet filter.
+    ///
+    /// Such a packet is looped back without going through the physical layer, so it is passed to
+    /// the hooks for both outgoing and incoming packets here. This method returns `None` if the
+    /// packet is dropped. Otherwise, it returns the packet information, which may have been
+    /// translated.
+    fn filter_looped_back(&self, mut packet_info: PacketInfo) -> Option<PacketInfo> {
+        let dst_addr = packet_info.dst_addr;
+        if !run_hooks::<E>(&[Hook::Output, Hook::PostRouting], &mut packet_info) {
+            return None;
+        }
+
+        // TODO: Reroute the packet if its destination address is translated to a non-local one.
+        if packet_info.dst_addr != dst_addr && !self.is_unicast_local(packet_info.dst_addr) {
+            return None;
+        }
+
+        if !run_hooks::<E>(&[Hook::PreRouting, Hook::Input], &mut packet_info) {
+            return None;
+        }
+
+        Some(packet_info)
+    }
+
+    fn packet_iface(&self) -> PacketIface {
+        PacketIface {
+            index: self.ifindex,
+            ipv4_addr: self.iface.context().ipv4_addr(),
+        }
+    }
+
+    fn filter_looped_back_tcp<'a>(
+        &self,
+        ip_repr: &IpRepr,
+        tcp_repr: &TcpRepr<'a>,
+    ) -> Option<(IpRepr, TcpRepr<'a>)> {
+        let packet_info = PacketInfo::from_tcp(self.packet_iface(), ip_repr, tcp_repr);
+        self.filter_looped_back(packet_info)
+            .map(|packet_info| packet_info.translate_tcp(ip_repr, tcp_repr))
+    }
+
+    fn filter_looped_back_udp(
+        &self,
+        ip_repr: &IpRepr,
+        udp_repr: &UdpRepr,
+    ) -> Option<(IpRepr, UdpRepr)> {
+        let packet_info = PacketInfo::from_udp(self.packet_iface(), ip_repr, udp_repr);
+        self.filter_looped_back(packet_info)
+            .map(|packet_info| packet_info.translate_udp(ip_repr, udp_repr))
+    }
+
+    /// Returns whether the destination address is an IPv6 multicast address that the local
+    /// interface listens to.
+    ///
+    /// Currently, these are the link-local all-nodes address and the solicited-node addresses of
+    /// the local interface.
+    fn is_multicast_local(&self, dst_addr: Ipv6Address) -> bool {
+        dst_addr == Ipv6Address::new(0xff02, 0, 0, 0, 0, 0, 0, 1)
+            || self.iface.context().has_solicited_node(dst_addr)
+    }
 }
 
 impl<E: Ext> PollContext<'_, E> {
@@ -370,15 +706,59 @@ impl<E: Ext> PollContext<'_, E> {
         T: TxToken,
         Q: FnMut(&Packet, &mut Context, T),
     {
+        let (did_something_igmp, tx_token) = self.dispatch_igmp(tx_token, dispatch_phy);
+
+        let Some(tx_token) = tx_token else {
+            return did_something_igmp;
+        };
+
         let (did_something_tcp, tx_token) = self.dispatch_tcp(tx_token, dispatch_phy);
 
         let Some(tx_token) = tx_token else {
-            return did_something_tcp;
+            return did_something_igmp || did_something_tcp;
+        };
+
+        let (did_something_udp, tx_token) = self.dispatch_udp(tx_token, dispatch_phy);
+
+        let Some(tx_token) = tx_token else {
+            return did_something_igmp || did_something_tcp || did_something_udp;
+        };
+
+        let (did_something_raw, _tx_token) = self.dispatch_raw(tx_token, dispatch_phy);
+
+        did_something_igmp || did_something_tcp || did_something_udp || did_something_raw
+    }
+
+    fn dispatch_igmp<T, Q>(&mut self, tx_token: T, dispatch_phy: &mut Q) -> (bool, Option<T>)
+    where
+        T: TxToken,
+        Q: FnMut(&Packet, &mut Context, T),
+    {
+        let Some((igmp_repr, dst_addr)) = self.multicast_groups.pop_pending_msg() else {
+            return (false, Some(tx_token));
+        };
+
+        // The message is dropped if the iface has no IPv4 address to send it from.
+        let Some(src_addr) = self.iface.context().ipv4_addr() else {
+            return (true, Some(tx_token));
         };
 
-        let (did_something_udp, _tx_token) = self.dispatch_udp(tx_token, dispatch_phy);
+        let ipv4_repr = Ipv4Repr {
+            src_addr,
+            dst_addr,
+            next_header: IpProtocol::Igmp,
+            payload_len: igmp_repr.buffer_len(),
+            // IGMP messages are never forwarded by routers. See
+            // <https://datatracker.ietf.org/doc/html/rfc2236#section-2>.
+            hop_limit: 1,
+        };
+        dispatch_phy(
+            &Packet::new_ipv4(ipv4_repr, IpPayload::Igmp(igmp_repr)),
+            self.iface.context_mut(),
+            tx_token,
+        );
 
-        did_something_tcp || did_something_udp
+        (true, None)
     }
 
     fn dispatch_tcp<T, Q>(&mut self, tx_token: T, dispatch_phy: &mut Q) -> (bool, Option<T>)
@@ -402,7 +782,13 @@ impl<E: Ext> PollContext<'_, E> {
 
             let (reply, became_dead) =
                 TcpConnectionBg::dispatch(&socket, &mut self.iface, |iface, ip_repr, tcp_repr| {
-                    let mut this = PollContext::new(iface, self.sockets, self.actions);
+                    let mut this = PollContext::new(
+                        iface,
+                        self.ifindex,
+                        self.sockets,
+                        self.multicast_groups,
+                        self.actions,
+                    );
 
                     if !this.is_unicast_local(ip_repr.dst_addr()) {
                         dispatch_phy(
@@ -413,22 +799,22 @@ impl<E: Ext> PollContext<'_, E> {
                         return None;
                     }
 
+                    let (ip_repr, tcp_repr) = this.filter_looped_back_tcp(ip_repr, tcp_repr)?;
+
                     if !socket.can_process(tcp_repr.dst_port) {
-                        return this.process_tcp(ip_repr, tcp_repr);
+                        return this.process_tcp(&ip_repr, &tcp_repr);
                     }
 
                     // We cannot call `process_tcp` now because it may cause deadlocks. We will copy
                     // the packet and call `process_tcp` after releasing the socket lock.
-                    deferred = Some((ip_repr.clone(), {
-                        let mut data = vec![0; tcp_repr.buffer_len()];
-                        tcp_repr.emit(
-                            &mut TcpPacket::new_unchecked(data.as_mut_slice()),
-                            &ip_repr.src_addr(),
-                            &ip_repr.dst_addr(),
-                            &ChecksumCapabilities::ignored(),
-                        );
-                        data
-                    }));
+                    let mut data = vec![0; tcp_repr.buffer_len()];
+                    tcp_repr.emit(
+                        &mut TcpPacket::new_unchecked(data.as_mut_slice()),
+                        &ip_repr.src_addr(),
+                        &ip_repr.dst_addr(),
+                        &ChecksumCapabilities::ignored(),
+                    );
+                    deferred = Some((ip_repr, data));
 
                     None
                 });
@@ -445,6 +831,7 @@ impl<E: Ext> PollContext<'_, E> {
                         &ip_repr,
                         &ip_payload,
                         &ChecksumCapabilities::ignored(),
+                        None,
                     ) {
                         dispatch_phy(&reply, self.iface.context_mut(), tx_token.take().unwrap());
                     }
@@ -457,8 +844,10 @@ impl<E: Ext> PollContext<'_, E> {
                     );
                 }
                 (None, Some((ip_repr, tcp_repr))) => {
-                    if let Some((new_ip_repr, new_tcp_repr)) =
-                        self.process_tcp_until_outgoing(&ip_repr, &tcp_repr)
+                    if let Some((ip_repr, tcp_repr)) =
+                        self.filter_looped_back_tcp(&ip_repr, &tcp_repr)
+                        && let Some((new_ip_repr, new_tcp_repr)) =
+                            self.process_tcp_until_outgoing(&ip_repr, &tcp_repr)
                     {
                         dispatch_phy(
                             &Packet::new(new_ip_repr, IpPayload::Tcp(new_tcp_repr)),
@@ -502,40 +891,62 @@ impl<E: Ext> PollContext<'_, E> {
             let (cx, pending) = self.iface.inner_mut();
             socket.dispatch(cx, |cx, ip_repr, udp_repr, udp_payload| {
                 let iface = PollableIfaceMut::new(cx, pending);
-                let mut this = PollContext::new(iface, self.sockets, &mut actions);
+                let mut this = PollContext::new(
+                    iface,
+                    self.ifindex,
+                    self.sockets,
+                    self.multicast_groups,
+                    &mut actions,
+                );
 
                 if ip_repr.dst_addr().is_broadcast() || !this.is_unicast_local(ip_repr.dst_addr()) {
+                    // Broadcast packets are always looped back, but multicast packets are looped
+                    // back only if `IP_MULTICAST_LOOP` is enabled and the group has been joined.
+                    let (ip_repr, should_loop_back) = match ip_repr {
+                        IpRepr::Ipv4(ipv4_repr) if ipv4_repr.dst_addr.is_multicast() => (
+                            IpRepr::Ipv4(Ipv4Repr {
+                                hop_limit: socket.multicast_ttl(),
+                                ..*ipv4_repr
+                            }),
+                            socket.multicast_loop()
+                                && this.multicast_groups.contains(ipv4_repr.dst_addr),
+                        ),
+                        _ => (ip_repr.clone(), ip_repr.dst_addr().is_broadcast()),
+                    };
                     dispatch_phy(
-                        &Packet::new(ip_repr.clone(), IpPayload::Udp(*udp_repr, udp_payload)),
+                        &Packet::new(ip_repr, IpPayload::Udp(*udp_repr, udp_payload)),
                         this.iface.context_mut(),
                         tx_token.take().unwrap(),
                     );
-                    if !ip_repr.dst_addr().is_broadcast() {
+                    if !should_loop_back {
                         return;
                     }
                 }
 
+                let Some((ip_repr, udp_repr)) = this.filter_looped_back_udp(ip_repr, udp_repr)
+                else {
+                    return;
+                };
+
                 if !socket.can_process(udp_repr.dst_port) {
                     // TODO: Generate the ICMP message here once we're able to handle incoming ICMP
                     // messages.
-                    let _ = this.process_udp(ip_repr, udp_repr, udp_payload);
+                    let _ = this.process_udp(&ip_repr, &udp_repr, udp_payload);
                     return;
                 }
 
                 // We cannot call `process_udp` now because it may cause deadlocks. We will copy
                 // the packet and call `process_udp` after releasing the socket lock.
-                deferred = Some((ip_repr.clone(), {
-                    let mut data = vec![0; udp_repr.header_len() + udp_payload.len()];
-                    udp_repr.emit(
-                        &mut UdpPacket::new_unchecked(&mut data),
-                        &ip_repr.src_addr(),
-                        &ip_repr.dst_addr(),
-                        udp_payload.len(),
-                        |payload| payload.copy_from_slice(udp_payload),
-                        &ChecksumCapabilities::ignored(),
-                    );
-                    data
-                }));
+                let mut data = vec![0; udp_repr.header_len() + udp_payload.len()];
+                udp_repr.emit(
+                    &mut UdpPacket::new_unchecked(&mut data),
+                    &ip_repr.src_addr(),
+                    &ip_repr.dst_addr(),
+                    udp_payload.len(),
+                    |payload| payload.copy_from_slice(udp_payload),
+                    &ChecksumCapabilities::ignored(),
+                );
+                deferred = Some((ip_repr, data));
             });
 
             if let Some((ip_repr, ip_payload)) = deferred
@@ -543,6 +954,7 @@ impl<E: Ext> PollContext<'_, E> {
                     &ip_repr,
                     &ip_payload,
                     &ChecksumCapabilities::ignored(),
+                    None,
                 )
             {
                 dispatch_phy(&reply, self.iface.context_mut(), tx_token.take().unwrap());
@@ -560,4 +972,88 @@ impl<E: Ext> PollContext<'_, E> {
 
         (did_something, tx_token)
     }
+
+    fn dispatch_raw<T, Q>(&mut self, tx_token: T, dispatch_phy: &mut Q) -> (bool, Option<T>)
+    where
+        T: TxToken,
+        Q: FnMut(&Packet, &mut Context, T),
+    {
+        let mut tx_token = Some(tx_token);
+        let mut did_something = false;
+
+        for socket in self.sockets.raw_socket_iter() {
+            if !socket.need_dispatch() {
+                continue;
+            }
+
+            let Some((mut ipv4_repr, payload)) = socket.dequeue_send() else {
+                continue;
+            };
+            did_something = true;
+
+            if ipv4_repr.src_addr.is_unspecified() {
+                // The packet is dropped if the iface has no IPv4 address to send it from.
+                let Some(src_addr) = self.iface.context().ipv4_addr() else {
+                    continue;
+                };
+                ipv4_repr.src_addr = src_addr;
+            }
+
+            let pkt = Packet::new_ipv4(ipv4_repr, IpPayload::Raw(&payload));
+            if !self.is_unicast_local(IpAddress::Ipv4(ipv4_repr.dst_addr)) {
+                dispatch_phy(&pkt, self.iface.context_mut(), tx_token.take().unwrap());
+                break;
+            }
+
+            // The hooks for incoming packets are run when the packet is processed below.
+            // TODO: Translate the packet if the packet filter requests it.
+            let mut packet_info = PacketInfo::from_packet(self.packet_iface(), &pkt);
+            if !run_hooks::<E>(&[Hook::Output, Hook::PostRouting], &mut packet_info) {
+                continue;
+            }
+
+            // The packet is processed as an incoming packet, which may generate replies to local
+            // addresses (e.g., ICMP echo replies). Like TCP packets, such replies are processed
+            // until a reply to another host is generated.
+            let mut data = emit_packet(&pkt);
+            loop {
+                let reply_data = {
+                    let Some(reply) =
+                        self.parse_and_process_ipv4(Ipv4Packet::new_unchecked(data.as_slice()))
+                    else {
+                        break;
+                    };
+
+                    if !self.is_unicast_local(reply.ip_repr().dst_addr()) {
+                        dispatch_phy(&reply, self.iface.context_mut(), tx_token.take().unwrap());
+                        break;
+                    }
+
+                    emit_packet(&reply)
+                };
+                data = reply_data;
+            }
+
+            if tx_token.is_none() {
+                break;
+            }
+        }
+
+        (did_something, tx_token)
+    }
+}
+
+/// Serializes a packet, including its IP header, with the checksums computed.
+fn emit_packet(pkt: &Packet) -> Vec<u8> {
+    let ip_repr = pkt.ip_repr();
+
+    let mut data = vec![0; ip_repr.buffer_len()];
+    ip_repr.emit(&mut data[..], &ChecksumCapabilities::default());
+    pkt.emit_payload(
+        &ip_repr,
+        &mut data[ip_repr.header_len()..],
+        &DeviceCapabilities::default(),
+    );
+
+    data
 }
@@ -1,11 +1,16 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use alloc::{collections::btree_set::BTreeSet, sync::Arc};
+use alloc::{collections::btree_set::BTreeSet, sync::Arc, vec::Vec};
 use core::{
     borrow::Borrow,
     sync::atomic::{AtomicU64, Ordering},
 };
 
+use smoltcp::wire::{
+    EthernetAddress, HardwareAddress, IpAddress, IpCidr, Ipv4Address, Ipv4Cidr, Ipv6Address,
+    Ipv6Cidr,
+};
+
 use crate::{
     ext::Ext,
     socket::{NeedIfacePoll, TcpConnectionBg},
@@ -35,6 +40,13 @@ impl<E: Ext> PollableIface<E> {
         }
     }
 
+    pub(super) fn ether_addr(&self) -> Option<EthernetAddress> {
+        match self.interface.hardware_addr() {
+            HardwareAddress::Ethernet(ether_addr) => Some(ether_addr),
+            _ => None,
+        }
+    }
+
     pub(super) fn ipv4_addr(&self) -> Option<smoltcp::wire::Ipv4Address> {
         self.interface.ipv4_addr()
     }
@@ -42,10 +54,149 @@ impl<E: Ext> PollableIface<E> {
     pub(super) fn prefix_len(&self) -> Option<u8> {
         self.interface
             .ip_addrs()
-            .first()
+            .iter()
+            .find(|ip_addr| matches!(ip_addr, IpCidr::Ipv4(_)))
             .map(|ip_addr| ip_addr.prefix_len())
     }
 
+    /// Adds an IPv4 address.
+    ///
+    /// This method returns `false` if there is already an IPv4 address or there is no room for it.
+    pub(super) fn add_ipv4_addr(&mut self, ipv4_cidr: Ipv4Cidr) -> bool {
+        let mut is_added = false;
+        self.interface.update_ip_addrs(|ip_addrs| {
+            if ip_addrs
+                .iter()
+                .any(|ip_addr| matches!(ip_addr, IpCidr::Ipv4(_)))
+            {
+                return;
+            }
+            is_added = ip_addrs.push(IpCidr::Ipv4(ipv4_cidr)).is_ok();
+        });
+        is_added
+    }
+
+    /// Removes an IPv4 address.
+    ///
+    /// This method returns `false` if the address does not exist.
+    pub(super) fn remove_ipv4_addr(&mut self, ipv4_addr: Ipv4Address) -> bool {
+        let mut is_removed = false;
+        self.interface.update_ip_addrs(|ip_addrs| {
+            if let Some(index) = ip_addrs
+                .iter()
+                .position(|ip_addr| ip_addr.address() == ipv4_addr.into())
+            {
+                ip_addrs.remove(index);
+                is_removed = true;
+            }
+        });
+        is_removed
+    }
+
+    pub(super) fn ipv6_addrs(&self) -> Vec<Ipv6Cidr> {
+        self.interface
+            .ip_addrs()
+            .iter()
+            .filter_map(|ip_addr| match ip_addr {
+                IpCidr::Ipv6(ipv6_cidr) => Some(*ipv6_cidr),
+                IpCidr::Ipv4(_) => None,
+            })
+            .collect()
+    }
+
+    /// Adds an IPv6 address.
+    ///
+    /// This method returns `false` if the address already exists or there is no room for it.
+    pub(super) fn add_ipv6_addr(&mut self, ipv6_cidr: Ipv6Cidr) -> bool {
+        let mut is_added = false;
+        self.interface.update_ip_addrs(|ip_addrs| {
+            if ip_addrs
+                .iter()
+                .any(|ip_addr| ip_addr.address() == ipv6_cidr.address().into())
+            {
+                return;
+            }
+            is_added = ip_addrs.push(IpCidr::Ipv6(ipv6_cidr)).is_ok();
+        });
+        is_added
+    }
+
+    /// Removes an IPv6 address.
+    ///
+    /// This method returns `false` if the address does not exist.
+    pub(super) fn remove_ipv6_addr(&mut self, ipv6_addr: Ipv6Address) -> bool {
+        let mut is_removed = false;
+        self.interface.update_ip_addrs(|ip_addrs| {
+            if let Some(index) = ip_addrs
+                .iter()
+                .position(|ip_addr| ip_addr.address() == ipv6_addr.into())
+            {
+                ip_addrs.remove(index);
+                is_removed = true;
+            }
+        });
+        is_removed
+    }
+
+    /// Returns the gateway of the default route that matches `is_version`.
+    fn default_gateway(&mut self, is_version: fn(&IpAddress) -> bool) -> Option<IpAddress> {
+        let mut gateway = None;
+        // `smoltcp` does not provide a way to iterate the routes without mutable access.
+        self.interface.routes_mut().update(|routes| {
+            gateway = routes
+                .iter()
+                .find(|route| route.cidr.prefix_len() == 0 && is_version(&route.via_router))
+                .map(|route| route.via_router);
+        });
+        gateway
+    }
+
+    pub(super) fn default_ipv4_gateway(&mut self) -> Option<Ipv4Address> {
+        match self.default_gateway(|addr| matches!(addr, IpAddress::Ipv4(_)))? {
+            IpAddress::Ipv4(gateway) => Some(gateway),
+            IpAddress::Ipv6(_) => None,
+        }
+    }
+
+    pub(super) fn set_default_ipv4_route(&mut self, gateway: Ipv4Address) {
+        // This can fail only if the routing table is full, which should not happen because there
+        // are only default routes.
+        let _ = self.interface.routes_mut().add_default_ipv4_route(gateway);
+    }
+
+    /// Removes the default route for IPv4 packets.
+    ///
+    /// This method returns `false` if there is no such route.
+    pub(super) fn remove_default_ipv4_route(&mut self) -> bool {
+        self.interface
+            .routes_mut()
+            .remove_default_ipv4_route()
+            .is_some()
+    }
+
+    pub(super) fn default_ipv6_gateway(&mut self) -> Option<Ipv6Address> {
+        match self.default_gateway(|addr| matches!(addr, IpAddress::Ipv6(_)))? {
+            IpAddress::Ipv6(gateway) => Some(gateway),
+            IpAddress::Ipv4(_) => None,
+        }
+    }
+
+    pub(super) fn set_default_ipv6_route(&mut self, gateway: Ipv6Address) {
+        // This can fail only if the routing table is full, which should not happen because there
+        // are only default routes.
+        let _ = self.interface.routes_mut().add_default_ipv6_route(gateway);
+    }
+
+    /// Removes the default route for IPv6 packets.
+    ///
+    /// This method returns `false` if there is no such route.
+    pub(super) fn remove_default_ipv6_route(&mut self) -> bool {
+        self.interface
+            .routes_mut()
+            .remove_default_ipv6_route()
+            .is_some()
+    }
+
     /// Returns the next poll time.
     pub(super) fn next_poll_at_ms(&self) -> Option<u64> {
         self.pending_conns.next_poll_at_ms()
@@ -1,34 +1,47 @@
 // SPDX-License-Identifier: MPL-2.0
 
+bitflags::bitflags! {
+    /// The ways in which a TCP/UDP port can be shared with other sockets.
+    pub struct PortReuse: u8 {
+        /// The port can be shared if no socket is listening on it (`SO_REUSEADDR`).
+        const ADDR = 1;
+        /// The port can be shared by a group of sockets, among which the incoming connections
+        /// and datagrams are distributed (`SO_REUSEPORT`).
+        const PORT = 2;
+    }
+}
+
 /// The configuration using for bind to a TCP/UDP port.
 pub enum BindPortConfig {
-    /// Binds to the specified non-reusable port.
-    CanReuse(u16),
-    /// Binds to the specified reusable port.
-    Specified(u16),
+    /// Binds to the specified port.
+    Specified(u16, PortReuse),
     /// Allocates an ephemeral port to bind.
-    Ephemeral(bool),
+    Ephemeral(PortReuse),
     /// Reuses the port of the listening socket.
-    Backlog(u16),
+    ///
+    /// The accepted socket inherits `PortReuse::PORT` from the listening socket, so other
+    /// sockets in the same group can still bind to the port.
+    Backlog(u16, PortReuse),
 }
 
 impl BindPortConfig {
     /// Creates new configuration using for bind to a TCP/UDP port.
-    pub fn new(port: u16, can_reuse: bool) -> Self {
-        match (port, can_reuse) {
-            (0, can_reuse) => Self::Ephemeral(can_reuse),
-            (_, true) => Self::CanReuse(port),
-            (_, false) => Self::Specified(port),
+    pub fn new(port: u16, reuse: PortReuse) -> Self {
+        match port {
+            0 => Self::Ephemeral(reuse),
+            _ => Self::Specified(port, reuse),
         }
     }
 
-    pub(super) fn can_reuse(&self) -> bool {
-        matches!(self, Self::CanReuse(_)) || matches!(self, Self::Ephemeral(true))
+    pub(super) fn reuse(&self) -> PortReuse {
+        match self {
+            Self::Specified(_, reuse) | Self::Ephemeral(reuse) | Self::Backlog(_, reuse) => *reuse,
+        }
     }
 
     pub(super) fn port(&self) -> Option<u16> {
         match self {
-            Self::CanReuse(port) | Self::Specified(port) | Self::Backlog(port) => Some(*port),
+            Self::Specified(port, _) | Self::Backlog(port, _) => Some(*port),
             Self::Ephemeral(_) => None,
         }
     }
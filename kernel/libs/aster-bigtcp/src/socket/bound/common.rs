@@ -138,4 +138,12 @@ impl<T: Inner<E>, E: Ext> SocketBg<T, E> {
     pub(crate) fn can_process(&self, dst_port: u16) -> bool {
         self.bound.port() == dst_port
     }
+
+    /// Returns whether the socket is in the `SO_REUSEPORT` group bound to the destination
+    /// endpoint of an incoming packet.
+    pub(crate) fn is_in_reuse_port_group(&self, dst_endpoint: &IpEndpoint) -> bool {
+        self.bound.port() == dst_endpoint.port
+            && self.bound.can_reuse_port()
+            && (self.bound.addr() == dst_endpoint.addr || self.bound.addr().is_unspecified())
+    }
 }
@@ -10,5 +10,5 @@ pub use tcp_conn::{ConnectState, RawTcpSocketExt, TcpConnection};
 pub(crate) use tcp_conn::{TcpConnectionBg, TcpProcessResult};
 pub use tcp_listen::TcpListener;
 pub(crate) use tcp_listen::TcpListenerBg;
-pub use udp::UdpSocket;
+pub use udp::{UdpRecvInfo, UdpSocket};
 pub(crate) use udp::UdpSocketBg;
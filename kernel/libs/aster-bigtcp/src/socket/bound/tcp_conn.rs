@@ -9,8 +9,9 @@ use core::ops::{Deref, DerefMut};
 use aster_softirq::BottomHalfDisabled;
 use ostd::sync::{SpinLock, SpinLockGuard};
 use smoltcp::{
+    iface::Context,
     socket::{PollAt, tcp::State},
-    time::Duration,
+    time::{Duration, Instant},
     wire::{IpEndpoint, IpRepr, TcpControl, TcpRepr},
 };
 
@@ -25,10 +26,11 @@ use crate::{
     iface::{BoundPort, PollKey, PollableIfaceMut},
     socket::{
         event::SocketEvents,
-        option::{RawTcpOption, RawTcpSetOption},
+        option::{CongestionControl, RawTcpOption, RawTcpSetOption},
         unbound::{RawTcpSocket, new_tcp_socket},
     },
     socket_table::ConnectionKey,
+    time::WallClock,
 };
 
 pub type TcpConnection<E> = Socket<TcpConnectionInner<E>, E>;
@@ -44,10 +46,19 @@ pub struct RawTcpSocketExt<E: Ext> {
     socket: Box<RawTcpSocket>,
     pub(super) listener: Option<Arc<TcpListenerBg<E>>>,
     has_connected: bool,
+    /// The time until which the connection is kept out of the accept queue of the listener.
+    ///
+    /// This is set if the listener defers accepting connections until data arrives (see
+    /// [`TcpListener::set_defer_accept`]).
+    ///
+    /// [`TcpListener::set_defer_accept`]: super::TcpListener::set_defer_accept
+    accept_deferred_until: Option<Instant>,
     /// Indicates if the receiving side of this socket is shut down by the user.
     is_recv_shut: bool,
     /// Indicates if the socket is closed by a RST packet.
     is_rst_closed: bool,
+    /// The time when the latest data arrived, as the duration since the Unix epoch.
+    recv_timestamp: Option<core::time::Duration>,
 }
 
 impl<E: Ext> Deref for RawTcpSocketExt<E> {
@@ -106,6 +117,13 @@ impl<E: Ext> RawTcpSocketExt<E> {
     pub fn is_rst_closed(&self) -> bool {
         self.is_rst_closed
     }
+
+    /// Returns the time when the latest data arrived, as the duration since the Unix epoch.
+    ///
+    /// This method returns `None` if no data has ever arrived.
+    pub fn recv_timestamp(&self) -> Option<core::time::Duration> {
+        self.recv_timestamp
+    }
 }
 
 define_boolean_value!(
@@ -118,10 +136,15 @@ impl<E: Ext> RawTcpSocketExt<E> {
     fn check_state(
         &mut self,
         this: &Arc<TcpConnectionBg<E>>,
+        now: Instant,
         old_state: State,
         old_recv_queue: usize,
         is_rst: bool,
     ) -> (SocketEvents, TcpConnBecameDead) {
+        if self.recv_queue() > old_recv_queue {
+            self.recv_timestamp = Some(E::WallClock::now());
+        }
+
         let became_dead = if self.state() != State::Established {
             // After the connection is closed by the user, no new data can be read, and such unread
             // data will immediately cause the connection to be reset.
@@ -147,26 +170,30 @@ impl<E: Ext> RawTcpSocketExt<E> {
             if self.state() == State::Closed && is_rst {
                 self.is_rst_closed = true;
             }
-            self.on_new_state(this)
+            self.on_new_state(this, now)
         } else {
             SocketEvents::empty()
         };
 
+        self.check_deferred_accept(this, now);
+
         (events, became_dead)
     }
 
-    fn on_new_state(&mut self, this: &Arc<TcpConnectionBg<E>>) -> SocketEvents {
+    fn on_new_state(&mut self, this: &Arc<TcpConnectionBg<E>>, now: Instant) -> SocketEvents {
         let may_send = self.may_send();
 
         if may_send && !self.has_connected {
             self.has_connected = true;
 
             if let Some(ref listener) = self.listener {
-                let mut backlog = listener.inner.backlog.lock();
-                if let Some(value) = backlog.connecting.remove(this.connection_key()) {
-                    backlog.connected.push(value);
+                let defer_accept = listener.inner.backlog.lock().defer_accept;
+                match defer_accept {
+                    Some(timeout) if self.recv_queue() == 0 && self.may_recv_new() => {
+                        self.accept_deferred_until = Some(now + timeout);
+                    }
+                    _ => self.move_to_accept_queue(this),
                 }
-                listener.notify_events(SocketEvents::CAN_RECV);
             }
         }
 
@@ -181,6 +208,53 @@ impl<E: Ext> RawTcpSocketExt<E> {
         events
     }
 
+    /// Moves the connection to the accept queue if it should no longer be deferred.
+    ///
+    /// Like Linux, the connection is accepted once some data arrives or the peer closes its
+    /// sending half. If neither happens before the timeout, the connection is accepted anyway.
+    fn check_deferred_accept(&mut self, this: &Arc<TcpConnectionBg<E>>, now: Instant) {
+        let Some(deadline) = self.accept_deferred_until else {
+            return;
+        };
+
+        if self.recv_queue() == 0 && self.may_recv_new() && now < deadline {
+            return;
+        }
+
+        self.accept_deferred_until = None;
+        self.move_to_accept_queue(this);
+    }
+
+    fn move_to_accept_queue(&self, this: &Arc<TcpConnectionBg<E>>) {
+        let Some(ref listener) = self.listener else {
+            return;
+        };
+
+        let mut backlog = listener.inner.backlog.lock();
+        if let Some(value) = backlog.connecting.remove(this.connection_key()) {
+            backlog.connected.push(value);
+        }
+        listener.notify_events(SocketEvents::CAN_RECV);
+    }
+
+    /// Returns when the socket should be polled next.
+    ///
+    /// This is similar to [`RawTcpSocket::poll_at`], but it also considers the deadline of the
+    /// deferred accept, so that the connection will be accepted in time.
+    fn poll_at_or_deferred(&self, cx: &mut Context) -> PollAt {
+        let poll_at = self.poll_at(cx);
+
+        let Some(deadline) = self.accept_deferred_until else {
+            return poll_at;
+        };
+
+        match poll_at {
+            PollAt::Now => PollAt::Now,
+            PollAt::Time(instant) => PollAt::Time(instant.min(deadline)),
+            PollAt::Ingress => PollAt::Time(deadline),
+        }
+    }
+
     /// Checks whether the TCP connection becomes dead.
     ///
     /// A TCP connection is considered dead when and only when the TCP socket is in the closed
@@ -233,8 +307,10 @@ impl<E: Ext> TcpConnectionInner<E> {
             socket,
             listener,
             has_connected: false,
+            accept_deferred_until: None,
             is_recv_shut: false,
             is_rst_closed: false,
+            recv_timestamp: None,
         };
 
         TcpConnectionInner {
@@ -533,10 +609,30 @@ impl<E: Ext> RawTcpSetOption for TcpConnection<E> {
         iface.update_next_poll_at_ms(&self.0, poll_at)
     }
 
+    fn set_timeout(&self, timeout: Option<Duration>) -> NeedIfacePoll {
+        let mut iface = self.iface().common().interface();
+        let mut socket = self.0.inner.lock();
+
+        socket.set_timeout(timeout);
+
+        let poll_at = socket.poll_at(iface.context_mut());
+        iface.update_next_poll_at_ms(&self.0, poll_at)
+    }
+
     fn set_nagle_enabled(&self, enabled: bool) {
         let mut socket = self.0.inner.lock();
         socket.set_nagle_enabled(enabled);
     }
+
+    fn set_congestion_control(&self, congestion_control: CongestionControl) {
+        let mut socket = self.0.inner.lock();
+        socket.set_congestion_control(congestion_control);
+    }
+
+    fn set_hop_limit(&self, hop_limit: u8) {
+        let mut socket = self.0.inner.lock();
+        socket.set_hop_limit(Some(hop_limit));
+    }
 }
 
 impl<E: Ext> TcpConnectionBg<E> {
@@ -618,13 +714,14 @@ impl<E: Ext> TcpConnectionBg<E> {
             Some((ip_repr, tcp_repr)) => TcpProcessResult::ProcessedWithReply(ip_repr, tcp_repr),
         };
 
+        let now = iface.context_mut().now();
         let (state_events, became_dead) =
-            socket.check_state(self, old_state, old_recv_queue, is_rst);
+            socket.check_state(self, now, old_state, old_recv_queue, is_rst);
         events |= state_events;
 
         self.notify_events(events);
 
-        let poll_at = socket.poll_at(iface.context_mut());
+        let poll_at = socket.poll_at_or_deferred(iface.context_mut());
         iface.update_next_poll_at_ms(self, poll_at);
 
         (result, became_dead)
@@ -666,13 +763,14 @@ impl<E: Ext> TcpConnectionBg<E> {
             reply = socket.process(iface.context_mut(), ip_repr, tcp_repr);
         }
 
+        let now = iface.context_mut().now();
         let (state_events, became_dead) =
-            socket.check_state(self, old_state, old_recv_queue, is_rst);
+            socket.check_state(self, now, old_state, old_recv_queue, is_rst);
         events |= state_events;
 
         self.notify_events(events);
 
-        let poll_at = socket.poll_at(iface.context_mut());
+        let poll_at = socket.poll_at_or_deferred(iface.context_mut());
         iface.update_next_poll_at_ms(self, poll_at);
 
         (reply, became_dead)
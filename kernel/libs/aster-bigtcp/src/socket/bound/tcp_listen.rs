@@ -17,9 +17,9 @@ use super::{
 use crate::{
     errors::tcp::ListenError,
     ext::Ext,
-    iface::{BindPortConfig, BoundPort, PollableIfaceMut},
+    iface::{BindPortConfig, BoundPort, PollableIfaceMut, PortReuse},
     socket::{
-        option::{RawTcpOption, RawTcpSetOption},
+        option::{CongestionControl, RawTcpOption, RawTcpSetOption},
         unbound::{RawTcpSocket, new_tcp_socket},
     },
     socket_table::{ConnectionKey, ListenerKey},
@@ -30,6 +30,7 @@ pub type TcpListener<E> = Socket<TcpListenerInner<E>, E>;
 pub struct TcpBacklog<E: Ext> {
     socket: Box<RawTcpSocket>,
     max_conn: usize,
+    pub(super) defer_accept: Option<Duration>,
     pub(super) connecting: BTreeMap<ConnectionKey, TcpConnection<E>>,
     pub(super) connected: Vec<TcpConnection<E>>,
 }
@@ -38,13 +39,16 @@ pub struct TcpBacklog<E: Ext> {
 pub struct TcpListenerInner<E: Ext> {
     pub(super) backlog: SpinLock<TcpBacklog<E>, BottomHalfDisabled>,
     listener_key: ListenerKey,
+    /// Whether the listener is in a `SO_REUSEPORT` group.
+    reuse_port: bool,
 }
 
 impl<E: Ext> TcpListenerInner<E> {
-    fn new(backlog: TcpBacklog<E>, listener_key: ListenerKey) -> Self {
+    fn new(backlog: TcpBacklog<E>, listener_key: ListenerKey, reuse_port: bool) -> Self {
         Self {
             backlog: SpinLock::new(backlog),
             listener_key,
+            reuse_port,
         }
     }
 }
@@ -81,8 +85,9 @@ impl<E: Ext> TcpListener<E> {
         let mut sockets = iface.common().sockets();
 
         let listener_key = ListenerKey::new(local_endpoint.addr, local_endpoint.port);
+        let reuse_port = bound.can_reuse_port();
 
-        if sockets.lookup_listener(&listener_key).is_some() {
+        if sockets.has_conflicting_listener(&listener_key, reuse_port) {
             return Err((bound, ListenError::AddressInUse));
         }
 
@@ -102,11 +107,12 @@ impl<E: Ext> TcpListener<E> {
             let backlog = TcpBacklog {
                 socket,
                 max_conn,
+                defer_accept: option.defer_accept,
                 connecting: BTreeMap::new(),
                 connected: Vec::new(),
             };
 
-            TcpListenerInner::new(backlog, listener_key)
+            TcpListenerInner::new(backlog, listener_key, reuse_port)
         };
 
         let listener = Self::new(bound, inner);
@@ -138,6 +144,18 @@ impl<E: Ext> TcpListener<E> {
         Some((accepted, remote_endpoint.unwrap()))
     }
 
+    /// Sets the maximum time to defer accepting a connection until data arrives.
+    ///
+    /// During this time, an established connection is kept out of the accept queue until it
+    /// receives some data or the peer closes its sending half. This implements the
+    /// `TCP_DEFER_ACCEPT` socket option.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds. The new value only
+    /// affects the connections that are established later.
+    pub fn set_defer_accept(&self, defer_accept: Option<Duration>) {
+        self.0.inner.backlog.lock().defer_accept = defer_accept;
+    }
+
     /// Returns whether there is a TCP connection to accept.
     ///
     /// It's the caller's responsibility to deal with race conditions when using this method.
@@ -178,16 +196,37 @@ impl<E: Ext> RawTcpSetOption for TcpListener<E> {
         NeedIfacePoll::FALSE
     }
 
+    fn set_timeout(&self, timeout: Option<Duration>) -> NeedIfacePoll {
+        let mut backlog = self.0.inner.backlog.lock();
+        backlog.socket.set_timeout(timeout);
+
+        NeedIfacePoll::FALSE
+    }
+
     fn set_nagle_enabled(&self, enabled: bool) {
         let mut backlog = self.0.inner.backlog.lock();
         backlog.socket.set_nagle_enabled(enabled);
     }
+
+    fn set_congestion_control(&self, congestion_control: CongestionControl) {
+        let mut backlog = self.0.inner.backlog.lock();
+        backlog.socket.set_congestion_control(congestion_control);
+    }
+
+    fn set_hop_limit(&self, hop_limit: u8) {
+        let mut backlog = self.0.inner.backlog.lock();
+        backlog.socket.set_hop_limit(Some(hop_limit));
+    }
 }
 
 impl<E: Ext> TcpListenerBg<E> {
     pub(crate) const fn listener_key(&self) -> &ListenerKey {
         &self.inner.listener_key
     }
+
+    pub(crate) const fn reuse_port(&self) -> bool {
+        self.inner.reuse_port
+    }
 }
 
 impl<E: Ext> TcpListenerBg<E> {
@@ -233,10 +272,15 @@ impl<E: Ext> TcpListenerBg<E> {
             socket
         };
 
+        let reuse = if self.inner.reuse_port {
+            PortReuse::PORT
+        } else {
+            PortReuse::empty()
+        };
         let conn = TcpConnection::new_cyclic(
             self.bound
                 .iface()
-                .bind(BindPortConfig::Backlog(self.bound.port()))
+                .bind(self.bound.addr(), BindPortConfig::Backlog(self.bound.port(), reuse))
                 .unwrap(),
             |weak| {
                 TcpConnectionInner::new(
@@ -1,14 +1,18 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use alloc::{boxed::Box, sync::Arc};
-use core::sync::atomic::{AtomicBool, Ordering};
+use alloc::{boxed::Box, collections::vec_deque::VecDeque, sync::Arc};
+use core::{
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicBool, AtomicU8, Ordering},
+    time::Duration,
+};
 
 use aster_softirq::BottomHalfDisabled;
 use ostd::sync::SpinLock;
 use smoltcp::{
     iface::Context,
     socket::udp::UdpMetadata,
-    wire::{IpRepr, UdpRepr},
+    wire::{IpAddress, IpRepr, UdpRepr},
 };
 
 use super::common::{Inner, Socket, SocketBg};
@@ -17,14 +21,48 @@ use crate::{
     ext::Ext,
     iface::BoundPort,
     socket::{RawUdpSocket, event::SocketEvents, unbound::new_udp_socket},
+    time::WallClock,
 };
 
 pub type UdpSocket<E> = Socket<UdpSocketInner, E>;
 
 /// States needed by [`UdpSocketBg`].
 pub struct UdpSocketInner {
-    socket: SpinLock<Box<RawUdpSocket>, BottomHalfDisabled>,
+    socket: SpinLock<RawUdpSocketExt, BottomHalfDisabled>,
     need_dispatch: AtomicBool,
+    /// The TTL of the outgoing IPv4 multicast packets.
+    multicast_ttl: AtomicU8,
+    /// Whether the outgoing IPv4 multicast packets are looped back to the local sockets.
+    multicast_loop: AtomicBool,
+}
+
+struct RawUdpSocketExt {
+    socket: Box<RawUdpSocket>,
+    /// The information about the packets in the receive buffer, in the same order as the packets.
+    recv_infos: VecDeque<UdpRecvInfo>,
+}
+
+/// The information about a received UDP packet.
+#[derive(Debug, Clone, Copy)]
+pub struct UdpRecvInfo {
+    /// The time when the packet arrived, as the duration since the Unix epoch.
+    pub timestamp: Duration,
+    /// The destination address of the packet.
+    pub dst_addr: IpAddress,
+}
+
+impl Deref for RawUdpSocketExt {
+    type Target = RawUdpSocket;
+
+    fn deref(&self) -> &Self::Target {
+        &self.socket
+    }
+}
+
+impl DerefMut for RawUdpSocketExt {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.socket
+    }
 }
 
 impl<E: Ext> Inner<E> for UdpSocketInner {
@@ -55,6 +93,9 @@ impl<E: Ext> UdpSocketBg<E> {
             return false;
         }
 
+        let old_recv_queue = socket.recv_queue();
+        let old_nr_packets = socket.recv_infos.len();
+
         socket.process(
             cx,
             smoltcp::phy::PacketMeta::default(),
@@ -63,6 +104,22 @@ impl<E: Ext> UdpSocketBg<E> {
             udp_payload,
         );
 
+        // The packet is dropped if the receive buffer is full. `smoltcp` does not tell us whether
+        // this happens, so we have to infer it from the buffer usage. For empty packets, the
+        // inference may be wrong because the padding in the buffer also takes up packet slots, in
+        // which case we record the information for a dropped packet.
+        let is_queued = if udp_payload.is_empty() {
+            old_nr_packets < socket.packet_recv_capacity()
+        } else {
+            socket.recv_queue() > old_recv_queue
+        };
+        if is_queued {
+            socket.recv_infos.push_back(UdpRecvInfo {
+                timestamp: E::WallClock::now(),
+                dst_addr: ip_repr.dst_addr(),
+            });
+        }
+
         self.notify_events(SocketEvents::CAN_RECV);
 
         true
@@ -96,6 +153,16 @@ impl<E: Ext> UdpSocketBg<E> {
     pub(crate) fn need_dispatch(&self) -> bool {
         self.inner.need_dispatch.load(Ordering::Relaxed)
     }
+
+    /// Returns the TTL of the outgoing IPv4 multicast packets.
+    pub(crate) fn multicast_ttl(&self) -> u8 {
+        self.inner.multicast_ttl.load(Ordering::Relaxed)
+    }
+
+    /// Returns whether the outgoing IPv4 multicast packets are looped back.
+    pub(crate) fn multicast_loop(&self) -> bool {
+        self.inner.multicast_loop.load(Ordering::Relaxed)
+    }
 }
 
 impl<E: Ext> UdpSocket<E> {
@@ -117,12 +184,17 @@ impl<E: Ext> UdpSocket<E> {
                 return Err((bound, err));
             }
 
-            socket
+            RawUdpSocketExt {
+                socket,
+                recv_infos: VecDeque::new(),
+            }
         };
 
         let inner = UdpSocketInner {
             socket: SpinLock::new(socket),
             need_dispatch: AtomicBool::new(false),
+            multicast_ttl: AtomicU8::new(DEFAULT_MULTICAST_TTL),
+            multicast_loop: AtomicBool::new(true),
         };
 
         let socket = Self::new(bound, inner);
@@ -166,19 +238,57 @@ impl<E: Ext> UdpSocket<E> {
 
     /// Receives some data.
     ///
+    /// The closure is also given the information about the packet (e.g., when it arrived).
+    ///
     /// Polling the iface is _not_ required after this method succeeds.
     pub fn recv<F, R>(&self, f: F) -> Result<R, smoltcp::socket::udp::RecvError>
     where
-        F: FnOnce(&[u8], UdpMetadata) -> R,
+        F: FnOnce(&[u8], UdpMetadata, UdpRecvInfo) -> R,
     {
         let mut socket = self.0.inner.socket.lock();
-
-        let (data, meta) = socket.recv()?;
-        let result = f(data, meta);
+        let socket = &mut *socket;
+
+        let (data, meta) = socket.socket.recv()?;
+        // `process` never misses the information about a queued packet, so this should not fail.
+        let info = socket.recv_infos.pop_front().unwrap_or_else(|| UdpRecvInfo {
+            timestamp: E::WallClock::now(),
+            dst_addr: self.bound_port().addr(),
+        });
+        let result = f(data, meta, info);
+
+        // Drop the information recorded for the dropped packets, if any (see `process`).
+        if !socket.can_recv() {
+            socket.recv_infos.clear();
+        }
 
         Ok(result)
     }
 
+    /// Sets the TTL of the outgoing unicast and broadcast packets.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds.
+    pub fn set_ttl(&self, ttl: u8) {
+        let mut socket = self.0.inner.socket.lock();
+        socket.set_hop_limit(Some(ttl));
+    }
+
+    /// Sets the TTL of the outgoing IPv4 multicast packets.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds.
+    pub fn set_multicast_ttl(&self, ttl: u8) {
+        self.0.inner.multicast_ttl.store(ttl, Ordering::Relaxed);
+    }
+
+    /// Sets whether the outgoing IPv4 multicast packets are looped back to the local sockets.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds.
+    pub fn set_multicast_loop(&self, multicast_loop: bool) {
+        self.0
+            .inner
+            .multicast_loop
+            .store(multicast_loop, Ordering::Relaxed);
+    }
+
     /// Calls `f` with an immutable reference to the associated [`RawUdpSocket`].
     //
     // NOTE: If a mutable reference is required, add a method above that correctly updates the next
@@ -191,3 +301,9 @@ impl<E: Ext> UdpSocket<E> {
         f(&socket)
     }
 }
+
+/// The default TTL of the outgoing IPv4 multicast packets.
+///
+/// Multicast packets are confined to the local network by default. See
+/// <https://datatracker.ietf.org/doc/html/rfc1112#section-6.1>.
+const DEFAULT_MULTICAST_TTL: u8 = 1;
@@ -3,14 +3,21 @@
 mod bound;
 mod event;
 mod option;
+mod packet;
+mod raw;
 mod unbound;
 
 pub use bound::{
-    ConnectState, NeedIfacePoll, RawTcpSocketExt, TcpConnection, TcpListener, UdpSocket,
+    ConnectState, NeedIfacePoll, RawTcpSocketExt, TcpConnection, TcpListener, UdpRecvInfo,
+    UdpSocket,
 };
 pub(crate) use bound::{TcpConnectionBg, TcpListenerBg, TcpProcessResult, UdpSocketBg};
 pub use event::{SocketEventObserver, SocketEvents};
-pub use option::{RawTcpOption, RawTcpSetOption};
+pub use option::{CongestionControl, RawTcpOption, RawTcpSetOption};
+pub(crate) use packet::PacketSocketBg;
+pub use packet::{FrameObserver, FrameType, PACKET_SEND_BUF_LEN, PacketSocket};
+pub(crate) use raw::RawIpSocketBg;
+pub use raw::{RAW_RECV_BUF_LEN, RAW_SEND_BUF_LEN, RawIpSocket, RawIpSocketKind};
 pub use unbound::{
     RawUdpSocket, TCP_RECV_BUF_LEN, TCP_SEND_BUF_LEN, UDP_RECV_PAYLOAD_LEN, UDP_SEND_PAYLOAD_LEN,
 };
@@ -1,5 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
+pub use smoltcp::socket::tcp::CongestionControl;
 use smoltcp::time::Duration;
 
 use super::{NeedIfacePoll, unbound::RawTcpSocket};
@@ -11,28 +12,61 @@ pub trait RawTcpSetOption {
     /// Polling the iface _may_ be required after this method succeeds.
     fn set_keep_alive(&self, interval: Option<Duration>) -> NeedIfacePoll;
 
+    /// Sets the timeout, after which the connection is aborted if the peer does not respond.
+    ///
+    /// Polling the iface _may_ be required after this method succeeds.
+    fn set_timeout(&self, timeout: Option<Duration>) -> NeedIfacePoll;
+
     /// Enables or disables Nagle’s Algorithm.
     ///
     /// Polling the iface is _not_ required after this method succeeds.
     fn set_nagle_enabled(&self, enabled: bool);
+
+    /// Sets the congestion control algorithm.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds.
+    fn set_congestion_control(&self, congestion_control: CongestionControl);
+
+    /// Sets the hop limit (i.e., the TTL for IPv4) of the outgoing packets.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds.
+    fn set_hop_limit(&self, hop_limit: u8);
 }
 
 /// Socket options on a raw socket.
 pub struct RawTcpOption {
     /// The keep alive interval.
     pub keep_alive: Option<Duration>,
+    /// The timeout.
+    pub timeout: Option<Duration>,
     /// Whether Nagle's algorithm is enabled.
     pub is_nagle_enabled: bool,
+    /// The congestion control algorithm.
+    pub congestion_control: CongestionControl,
+    /// The maximum time to defer accepting a connection until data arrives.
+    ///
+    /// This is only used by listeners (see [`TcpListener::set_defer_accept`]).
+    ///
+    /// [`TcpListener::set_defer_accept`]: super::TcpListener::set_defer_accept
+    pub defer_accept: Option<Duration>,
+    /// The hop limit (i.e., the TTL for IPv4) of the outgoing packets.
+    pub hop_limit: u8,
 }
 
 impl RawTcpOption {
     pub(super) fn apply(&self, socket: &mut RawTcpSocket) {
         socket.set_keep_alive(self.keep_alive);
+        socket.set_timeout(self.timeout);
         socket.set_nagle_enabled(self.is_nagle_enabled);
+        socket.set_congestion_control(self.congestion_control);
+        socket.set_hop_limit(Some(self.hop_limit));
     }
 
     pub(super) fn inherit(from: &RawTcpSocket, to: &mut RawTcpSocket) {
         to.set_keep_alive(from.keep_alive());
+        to.set_timeout(from.timeout());
         to.set_nagle_enabled(from.nagle_enabled());
+        to.set_congestion_control(from.congestion_control());
+        to.set_hop_limit(from.hop_limit());
     }
 }
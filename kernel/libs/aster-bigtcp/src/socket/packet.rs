@@ -0,0 +1,157 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{collections::vec_deque::VecDeque, sync::Arc, vec::Vec};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use aster_softirq::BottomHalfDisabled;
+use ostd::sync::SpinLock;
+use smoltcp::wire::ETHERNET_HEADER_LEN;
+
+use crate::{
+    errors::packet::SendError,
+    ext::Ext,
+    iface::Iface,
+    socket::event::{SocketEventObserver, SocketEvents},
+};
+
+/// A packet socket attached to an iface.
+///
+/// A packet socket observes all the frames that are received or sent on the iface, and sends
+/// frames on the iface directly. The frames always start with Ethernet headers. For ifaces that do
+/// not use Ethernet (e.g., the loopback iface), Ethernet headers with zero addresses are added to
+/// the observed frames and removed from the sent frames, as Linux does for the loopback iface.
+pub struct PacketSocket<E: Ext>(Arc<PacketSocketBg<E>>);
+
+/// The type of a frame observed by packet sockets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameType {
+    /// A frame sent to this host.
+    Host,
+    /// A frame sent to the broadcast address.
+    Broadcast,
+    /// A frame sent to a multicast address.
+    Multicast,
+    /// A frame sent by this host.
+    Outgoing,
+}
+
+/// An observer that will be invoked whenever a packet socket observes a frame.
+pub trait FrameObserver: SocketEventObserver {
+    /// Notifies that a frame is received or sent on the iface.
+    fn on_frame(&self, frame: &[u8], frame_type: FrameType);
+}
+
+pub(crate) struct PacketSocketBg<E: Ext> {
+    iface: Arc<dyn Iface<E>>,
+    send_queue: SpinLock<PacketSendQueue, BottomHalfDisabled>,
+    need_dispatch: AtomicBool,
+    observer: E::PacketEventObserver,
+}
+
+struct PacketSendQueue {
+    frames: VecDeque<Vec<u8>>,
+    len: usize,
+}
+
+impl<E: Ext> PacketSocketBg<E> {
+    /// Processes a frame that is received or sent on the iface.
+    pub(crate) fn process(&self, frame: &[u8], frame_type: FrameType) {
+        self.observer.on_frame(frame, frame_type);
+    }
+
+    /// Dequeues an outgoing frame.
+    pub(crate) fn dequeue_send(&self) -> Option<Vec<u8>> {
+        let mut send_queue = self.send_queue.lock();
+
+        let frame = send_queue.frames.pop_front()?;
+        send_queue.len -= frame.len();
+        self.need_dispatch
+            .store(!send_queue.frames.is_empty(), Ordering::Relaxed);
+        drop(send_queue);
+
+        // Dequeuing a frame means that we can queue more frames.
+        self.observer.on_events(SocketEvents::CAN_SEND);
+
+        Some(frame)
+    }
+
+    /// Returns whether the socket _may_ generate an outgoing frame.
+    ///
+    /// The check is intended to be lock-free and fast, but may have false positives.
+    pub(crate) fn need_dispatch(&self) -> bool {
+        self.need_dispatch.load(Ordering::Relaxed)
+    }
+}
+
+impl<E: Ext> PacketSocket<E> {
+    /// Creates a packet socket attached to the iface.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds.
+    pub fn new(iface: Arc<dyn Iface<E>>, observer: E::PacketEventObserver) -> Self {
+        let send_queue = PacketSendQueue {
+            frames: VecDeque::new(),
+            len: 0,
+        };
+
+        let socket = Self(Arc::new(PacketSocketBg {
+            iface,
+            send_queue: SpinLock::new(send_queue),
+            need_dispatch: AtomicBool::new(false),
+            observer,
+        }));
+
+        socket
+            .iface()
+            .common()
+            .register_packet_socket(socket.0.clone());
+
+        socket
+    }
+
+    /// Returns a reference to the iface.
+    pub fn iface(&self) -> &Arc<dyn Iface<E>> {
+        &self.0.iface
+    }
+
+    /// Sends a frame, which must start with an Ethernet header.
+    ///
+    /// Polling the iface is _always_ required after this method succeeds.
+    //
+    // TODO: The frames are dropped if the iface does not use Ethernet and the frames do not
+    // contain IP packets.
+    pub fn send(&self, frame: Vec<u8>) -> Result<(), SendError> {
+        if frame.len() < ETHERNET_HEADER_LEN {
+            return Err(SendError::InvalidHeader);
+        }
+        if frame.len() > self.0.iface.mtu() + ETHERNET_HEADER_LEN {
+            return Err(SendError::TooLarge);
+        }
+
+        let mut send_queue = self.0.send_queue.lock();
+
+        if !send_queue.frames.is_empty() && send_queue.len + frame.len() > PACKET_SEND_BUF_LEN {
+            return Err(SendError::BufferFull);
+        }
+
+        send_queue.len += frame.len();
+        send_queue.frames.push_back(frame);
+        self.0.need_dispatch.store(true, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Returns whether more frames can be sent.
+    pub fn can_send(&self) -> bool {
+        self.0.send_queue.lock().len < PACKET_SEND_BUF_LEN
+    }
+}
+
+impl<E: Ext> Drop for PacketSocket<E> {
+    fn drop(&mut self) {
+        // A packet socket can be removed immediately.
+        self.iface().common().remove_packet_socket(&self.0);
+    }
+}
+
+/// The send buffer size of packet sockets.
+pub const PACKET_SEND_BUF_LEN: usize = 65536;
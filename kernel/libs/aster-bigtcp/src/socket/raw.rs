@@ -0,0 +1,459 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{
+    collections::{btree_set::BTreeSet, vec_deque::VecDeque},
+    sync::Arc,
+    vec::Vec,
+};
+use core::sync::atomic::{AtomicBool, AtomicU8, AtomicU32, Ordering};
+
+use aster_softirq::BottomHalfDisabled;
+use ostd::sync::SpinLock;
+use smoltcp::{
+    phy::ChecksumCapabilities,
+    wire::{
+        IPV4_HEADER_LEN, Icmpv4Message, Icmpv4Packet, IpProtocol, Ipv4Address, Ipv4Packet,
+        Ipv4Repr,
+    },
+};
+
+use crate::{
+    errors::{
+        BindError,
+        raw::{RecvError, SendError},
+    },
+    ext::Ext,
+    iface::Iface,
+    socket::event::{SocketEventObserver, SocketEvents},
+};
+
+/// A raw IPv4 socket bound to an iface.
+///
+/// Depending on its [`RawIpSocketKind`], the socket is either a raw socket (`SOCK_RAW`), which
+/// exchanges the IP packets of a protocol, or a ping socket (`SOCK_DGRAM` with `IPPROTO_ICMP`),
+/// which sends ICMP echo requests and receives the matching ICMP echo replies.
+pub struct RawIpSocket<E: Ext>(Arc<RawIpSocketBg<E>>);
+
+/// The kind of a [`RawIpSocket`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawIpSocketKind {
+    /// A raw socket that sends and receives the IP packets of the protocol.
+    ///
+    /// The received packets include the IP headers.
+    Raw(IpProtocol),
+    /// A ping socket that sends ICMP echo requests with the identifier and receives the ICMP echo
+    /// replies with the same identifier.
+    ///
+    /// The received packets are ICMP messages without the IP headers.
+    Ping(u16),
+}
+
+pub(crate) struct RawIpSocketBg<E: Ext> {
+    iface: Arc<dyn Iface<E>>,
+    local_addr: Ipv4Address,
+    kind: RawIpSocketKind,
+    /// The connected remote address, or the unspecified address if the socket is not connected.
+    remote_addr: AtomicU32,
+    /// The ICMP message types that are not received, as a bitmap.
+    icmp_filter: AtomicU32,
+    /// The TTL of the outgoing packets.
+    hop_limit: AtomicU8,
+    /// Whether the IP headers of the outgoing packets are provided by the user.
+    header_included: AtomicBool,
+    queues: SpinLock<RawIpSocketQueues, BottomHalfDisabled>,
+    need_dispatch: AtomicBool,
+    observer: E::RawEventObserver,
+}
+
+struct RawIpSocketQueues {
+    recv_queue: VecDeque<(Ipv4Address, Vec<u8>)>,
+    recv_len: usize,
+    send_queue: VecDeque<(Ipv4Repr, Vec<u8>)>,
+    send_len: usize,
+}
+
+impl<E: Ext> RawIpSocketBg<E> {
+    /// Tries to process an incoming IP packet and returns whether the packet is processed.
+    ///
+    /// Only raw sockets process the packets here. Ping sockets process the ICMP echo replies in
+    /// [`Self::process_echo_reply`].
+    pub(crate) fn process(
+        &self,
+        ipv4_repr: &Ipv4Repr,
+        ip_packet: &[u8],
+        ip_payload: &[u8],
+    ) -> bool {
+        let RawIpSocketKind::Raw(protocol) = self.kind else {
+            return false;
+        };
+
+        if protocol != ipv4_repr.next_header || !self.accepts(ipv4_repr) {
+            return false;
+        }
+
+        if protocol == IpProtocol::Icmp
+            && let Some(&msg_type) = ip_payload.first()
+            && msg_type < 32
+            && self.icmp_filter.load(Ordering::Relaxed) & (1 << msg_type) != 0
+        {
+            return false;
+        }
+
+        self.enqueue_recv(ipv4_repr.src_addr, ip_packet);
+
+        true
+    }
+
+    /// Tries to process an incoming ICMP echo reply and returns whether the reply is processed.
+    pub(crate) fn process_echo_reply(
+        &self,
+        ipv4_repr: &Ipv4Repr,
+        ident: u16,
+        icmp_msg: &[u8],
+    ) -> bool {
+        if self.kind != RawIpSocketKind::Ping(ident) || !self.accepts(ipv4_repr) {
+            return false;
+        }
+
+        self.enqueue_recv(ipv4_repr.src_addr, icmp_msg);
+
+        true
+    }
+
+    fn accepts(&self, ipv4_repr: &Ipv4Repr) -> bool {
+        if !self.local_addr.is_unspecified() && self.local_addr != ipv4_repr.dst_addr {
+            return false;
+        }
+
+        // Like Linux, only raw sockets filter the packets by the connected address.
+        let remote_addr = Ipv4Address::from_bits(self.remote_addr.load(Ordering::Relaxed));
+        !matches!(self.kind, RawIpSocketKind::Raw(_))
+            || remote_addr.is_unspecified()
+            || remote_addr == ipv4_repr.src_addr
+    }
+
+    fn enqueue_recv(&self, src_addr: Ipv4Address, data: &[u8]) {
+        let mut queues = self.queues.lock();
+
+        // Drop the packet if the receive buffer is full.
+        if !queues.recv_queue.is_empty() && queues.recv_len + data.len() > RAW_RECV_BUF_LEN {
+            return;
+        }
+
+        queues.recv_queue.push_back((src_addr, data.to_vec()));
+        queues.recv_len += data.len();
+        drop(queues);
+
+        self.observer.on_events(SocketEvents::CAN_RECV);
+    }
+
+    /// Dequeues an outgoing packet.
+    ///
+    /// The source address of the packet is unspecified if the socket is not bound to an address
+    /// and the IP header is not provided by the user.
+    pub(crate) fn dequeue_send(&self) -> Option<(Ipv4Repr, Vec<u8>)> {
+        let mut queues = self.queues.lock();
+
+        let (ipv4_repr, payload) = queues.send_queue.pop_front()?;
+        queues.send_len -= payload.len();
+        self.need_dispatch
+            .store(!queues.send_queue.is_empty(), Ordering::Relaxed);
+        drop(queues);
+
+        // Dequeuing a packet means that we can queue more packets.
+        self.observer.on_events(SocketEvents::CAN_SEND);
+
+        Some((ipv4_repr, payload))
+    }
+
+    /// Returns whether the socket _may_ generate an outgoing packet.
+    ///
+    /// The check is intended to be lock-free and fast, but may have false positives.
+    pub(crate) fn need_dispatch(&self) -> bool {
+        self.need_dispatch.load(Ordering::Relaxed)
+    }
+
+    /// Returns the identifier if the socket is a ping socket.
+    pub(crate) fn ping_ident(&self) -> Option<u16> {
+        match self.kind {
+            RawIpSocketKind::Raw(_) => None,
+            RawIpSocketKind::Ping(ident) => Some(ident),
+        }
+    }
+}
+
+impl<E: Ext> RawIpSocket<E> {
+    /// Creates a raw socket that sends and receives the IP packets of the protocol.
+    ///
+    /// If `local_addr` is unspecified, the socket receives the packets sent to any address of the
+    /// iface.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds.
+    pub fn new_raw(
+        iface: Arc<dyn Iface<E>>,
+        local_addr: Ipv4Address,
+        protocol: IpProtocol,
+        observer: E::RawEventObserver,
+    ) -> Self {
+        let socket = Self::new(iface, local_addr, RawIpSocketKind::Raw(protocol), observer);
+
+        socket
+            .iface()
+            .common()
+            .register_raw_socket(socket.0.clone());
+
+        socket
+    }
+
+    /// Creates a ping socket with the identifier.
+    ///
+    /// If `ident` is `None`, an unused identifier will be picked up for the socket.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds.
+    pub fn new_ping(
+        iface: Arc<dyn Iface<E>>,
+        local_addr: Ipv4Address,
+        ident: Option<u16>,
+        observer: E::RawEventObserver,
+    ) -> Result<Self, BindError> {
+        let mut sockets = iface.common().sockets();
+
+        let used_idents = sockets
+            .raw_socket_iter()
+            .filter_map(|socket| socket.ping_ident())
+            .collect::<BTreeSet<_>>();
+        let ident = match ident {
+            Some(ident) if used_idents.contains(&ident) => return Err(BindError::InUse),
+            Some(ident) => ident,
+            None => (1..=u16::MAX)
+                .find(|ident| !used_idents.contains(ident))
+                .ok_or(BindError::Exhausted)?,
+        };
+
+        let socket = Self::new(
+            iface.clone(),
+            local_addr,
+            RawIpSocketKind::Ping(ident),
+            observer,
+        );
+        sockets.insert_raw_socket(socket.0.clone());
+        drop(sockets);
+
+        Ok(socket)
+    }
+
+    fn new(
+        iface: Arc<dyn Iface<E>>,
+        local_addr: Ipv4Address,
+        kind: RawIpSocketKind,
+        observer: E::RawEventObserver,
+    ) -> Self {
+        let queues = RawIpSocketQueues {
+            recv_queue: VecDeque::new(),
+            recv_len: 0,
+            send_queue: VecDeque::new(),
+            send_len: 0,
+        };
+
+        Self(Arc::new(RawIpSocketBg {
+            iface,
+            local_addr,
+            kind,
+            remote_addr: AtomicU32::new(Ipv4Address::UNSPECIFIED.to_bits()),
+            icmp_filter: AtomicU32::new(0),
+            hop_limit: AtomicU8::new(DEFAULT_HOP_LIMIT),
+            header_included: AtomicBool::new(false),
+            queues: SpinLock::new(queues),
+            need_dispatch: AtomicBool::new(false),
+            observer,
+        }))
+    }
+
+    /// Returns a reference to the iface.
+    pub fn iface(&self) -> &Arc<dyn Iface<E>> {
+        &self.0.iface
+    }
+
+    /// Returns the local address, which may be unspecified.
+    pub fn local_addr(&self) -> Ipv4Address {
+        self.0.local_addr
+    }
+
+    /// Returns the kind of the socket.
+    pub fn kind(&self) -> RawIpSocketKind {
+        self.0.kind
+    }
+
+    /// Connects to the remote address.
+    ///
+    /// Raw sockets will only receive packets from the remote address after this method is called.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds.
+    pub fn connect(&self, remote_addr: Ipv4Address) {
+        self.0
+            .remote_addr
+            .store(remote_addr.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Sets the ICMP message types that are not received by raw ICMP sockets, as a bitmap.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds.
+    pub fn set_icmp_filter(&self, icmp_filter: u32) {
+        self.0.icmp_filter.store(icmp_filter, Ordering::Relaxed);
+    }
+
+    /// Sets the TTL of the outgoing packets.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds.
+    pub fn set_hop_limit(&self, hop_limit: u8) {
+        self.0.hop_limit.store(hop_limit, Ordering::Relaxed);
+    }
+
+    /// Sets whether the IP headers of the outgoing packets are provided by the user.
+    ///
+    /// This only affects raw sockets.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds.
+    pub fn set_header_included(&self, header_included: bool) {
+        self.0
+            .header_included
+            .store(header_included, Ordering::Relaxed);
+    }
+
+    /// Sends a packet.
+    ///
+    /// For ping sockets, the packet must be an ICMP echo request, whose identifier and checksum
+    /// will be filled in. For raw sockets, the packet is the IP payload, unless the IP header is
+    /// provided by the user (see [`Self::set_header_included`]), in which case the packet starts
+    /// with the IP header and `dst_addr` is ignored.
+    ///
+    /// Polling the iface is _always_ required after this method succeeds.
+    pub fn send(&self, dst_addr: Ipv4Address, mut packet: Vec<u8>) -> Result<(), SendError> {
+        let header_included = matches!(self.0.kind, RawIpSocketKind::Raw(_))
+            && self.0.header_included.load(Ordering::Relaxed);
+
+        // TODO: Support IP fragmentation to send packets larger than the MTU.
+        let ip_len = if header_included {
+            packet.len()
+        } else {
+            packet.len() + IPV4_HEADER_LEN
+        };
+        if ip_len > self.0.iface.mtu().min(u16::MAX as usize) {
+            return Err(SendError::TooLarge);
+        }
+
+        let ipv4_repr = match self.0.kind {
+            RawIpSocketKind::Raw(_) if header_included => {
+                let (ipv4_repr, header_len) = parse_user_header(&mut packet)?;
+                packet.drain(..header_len);
+                ipv4_repr
+            }
+            RawIpSocketKind::Raw(protocol) => self.new_ipv4_repr(dst_addr, protocol, &packet),
+            RawIpSocketKind::Ping(ident) => {
+                let mut icmp_packet = Icmpv4Packet::new_checked(packet.as_mut_slice())
+                    .map_err(|_| SendError::InvalidHeader)?;
+                if icmp_packet.msg_type() != Icmpv4Message::EchoRequest
+                    || icmp_packet.msg_code() != 0
+                {
+                    return Err(SendError::InvalidHeader);
+                }
+                icmp_packet.set_echo_ident(ident);
+                icmp_packet.fill_checksum();
+
+                self.new_ipv4_repr(dst_addr, IpProtocol::Icmp, &packet)
+            }
+        };
+
+        let mut queues = self.0.queues.lock();
+
+        if !queues.send_queue.is_empty() && queues.send_len + packet.len() > RAW_SEND_BUF_LEN {
+            return Err(SendError::BufferFull);
+        }
+
+        queues.send_len += packet.len();
+        queues.send_queue.push_back((ipv4_repr, packet));
+        self.0.need_dispatch.store(true, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    fn new_ipv4_repr(
+        &self,
+        dst_addr: Ipv4Address,
+        protocol: IpProtocol,
+        payload: &[u8],
+    ) -> Ipv4Repr {
+        Ipv4Repr {
+            src_addr: self.0.local_addr,
+            dst_addr,
+            next_header: protocol,
+            payload_len: payload.len(),
+            hop_limit: self.0.hop_limit.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Receives a packet.
+    ///
+    /// `f` is called with the packet and the source address. For ping sockets, the packet is an
+    /// ICMP echo reply. For raw sockets, the packet includes the IP header.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds.
+    pub fn recv<F, R>(&self, f: F) -> Result<R, RecvError>
+    where
+        F: FnOnce(&[u8], Ipv4Address) -> R,
+    {
+        let mut queues = self.0.queues.lock();
+
+        let (src_addr, data) = queues.recv_queue.pop_front().ok_or(RecvError::Exhausted)?;
+        queues.recv_len -= data.len();
+        drop(queues);
+
+        Ok(f(&data, src_addr))
+    }
+
+    /// Returns whether there are packets to receive.
+    pub fn can_recv(&self) -> bool {
+        !self.0.queues.lock().recv_queue.is_empty()
+    }
+
+    /// Returns whether more packets can be sent.
+    pub fn can_send(&self) -> bool {
+        self.0.queues.lock().send_len < RAW_SEND_BUF_LEN
+    }
+}
+
+impl<E: Ext> Drop for RawIpSocket<E> {
+    fn drop(&mut self) {
+        // A raw socket can be removed immediately.
+        self.iface().common().remove_raw_socket(&self.0);
+    }
+}
+
+/// Parses and completes the IP header provided by the user.
+///
+/// Like Linux, the total length field is always filled in, and the header checksum is always
+/// computed. The source address is filled in if it is unspecified.
+//
+// TODO: The packet is sent with the header regenerated from the parsed fields, so the IP options,
+// the identification, the type of service, and the flags in the user's header are not preserved.
+fn parse_user_header(packet: &mut [u8]) -> Result<(Ipv4Repr, usize), SendError> {
+    if packet.len() < IPV4_HEADER_LEN {
+        return Err(SendError::InvalidHeader);
+    }
+
+    let total_len = packet.len() as u16;
+    Ipv4Packet::new_unchecked(&mut *packet).set_total_len(total_len);
+
+    let ipv4_packet = Ipv4Packet::new_checked(&*packet).map_err(|_| SendError::InvalidHeader)?;
+    let ipv4_repr = Ipv4Repr::parse(&ipv4_packet, &ChecksumCapabilities::ignored())
+        .map_err(|_| SendError::InvalidHeader)?;
+
+    Ok((ipv4_repr, ipv4_packet.header_len() as usize))
+}
+
+// Raw socket buffer sizes:
+pub const RAW_SEND_BUF_LEN: usize = 65536;
+pub const RAW_RECV_BUF_LEN: usize = 65536;
+
+/// The default TTL of the outgoing packets.
+const DEFAULT_HOP_LIMIT: u8 = 64;
@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
-//! This module defines the socket table, which manages all TCP and UDP sockets,
+//! This module defines the socket table, which manages all TCP, UDP, and raw sockets,
 //! for efficiently inserting, looking up, and removing sockets.
 
 use alloc::{boxed::Box, sync::Arc, vec::Vec};
@@ -12,17 +12,18 @@ use smoltcp::wire::{IpAddress, IpEndpoint, IpListenEndpoint};
 
 use crate::{
     ext::Ext,
-    socket::{TcpConnectionBg, TcpListenerBg, UdpSocketBg},
+    socket::{RawIpSocketBg, TcpConnectionBg, TcpListenerBg, UdpSocketBg},
     wire::PortNum,
 };
 
 pub type SocketHash = u32;
 
-/// A unique key for identifying a `TcpListener`.
+/// A key for identifying a `TcpListener`.
 ///
 /// Note that two `TcpListener`s cannot listen on the same address
-/// even if both sockets set SO_REUSEADDR to true,
-/// so there cannot be multiple listeners with the same `ListenerKey`.
+/// even if both sockets set SO_REUSEADDR to true.
+/// Multiple listeners can only have the same `ListenerKey` if all of them set SO_REUSEPORT to true,
+/// in which case they form a group and the incoming connections are distributed among them.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) struct ListenerKey {
     addr: IpAddress,
@@ -103,26 +104,37 @@ const fn hash_local_remote(
     remote_addr: IpAddress,
     remote_port: PortNum,
 ) -> SocketHash {
-    // FIXME: Deal with IPv6 addresses once IPv6 is supported.
-    let IpAddress::Ipv4(local_ipv4) = local_addr;
-    let IpAddress::Ipv4(remote_ipv4) = remote_addr;
-
     jhash_3vals(
-        local_ipv4.to_bits(),
-        remote_ipv4.to_bits(),
+        fold_addr(local_addr),
+        fold_addr(remote_addr),
         (local_port as u32).wrapping_shl(16) | remote_port as u32,
         HASH_SECRET.wrapping_add(NET_HASHMIX),
     )
 }
 
 const fn hash_addr_port(addr: IpAddress, port: PortNum) -> SocketHash {
-    // FIXME: Deal with IPv6 addresses once IPv6 is supported.
-    let IpAddress::Ipv4(ipv4_addr) = addr;
+    jhash_1vals(fold_addr(addr), NET_HASHMIX) ^ (port as u32)
+}
 
-    jhash_1vals(ipv4_addr.to_bits(), NET_HASHMIX) ^ (port as u32)
+/// Folds an IP address into a 32-bit value.
+///
+/// IPv6 addresses are folded in the same way as `__ipv6_addr_jhash` in Linux.
+const fn fold_addr(addr: IpAddress) -> u32 {
+    match addr {
+        IpAddress::Ipv4(ipv4_addr) => ipv4_addr.to_bits(),
+        IpAddress::Ipv6(ipv6_addr) => {
+            let bits = ipv6_addr.to_bits();
+            jhash_3vals(
+                ((bits >> 96) as u32) ^ ((bits >> 64) as u32),
+                (bits >> 32) as u32,
+                bits as u32,
+                HASH_SECRET,
+            )
+        }
+    }
 }
 
-/// The socket table manages TCP and UDP sockets.
+/// The socket table manages TCP, UDP, and raw sockets.
 ///
 /// Unlike the Linux inet hashtable, which is shared across a single network namespace,
 /// this table is currently limited to a single interface.
@@ -142,6 +154,9 @@ pub(crate) struct SocketTable<E: Ext> {
     // Note that multiple UDP sockets can be bound to the same address,
     // so we cannot use (addr, port) as a _unique_ key for UDP sockets.
     udp_sockets: Vec<Arc<UdpSocketBg<E>>>,
+    // Raw sockets (including ping sockets) are not bound to ports, so they are kept in a list,
+    // like UDP sockets.
+    raw_sockets: Vec<Arc<RawIpSocketBg<E>>>,
 }
 
 // On Linux, the number of buckets is determined at runtime based on the available memory.
@@ -167,37 +182,35 @@ impl<E: Ext> SocketTable<E> {
 
         let udp_sockets = Vec::new();
 
+        let raw_sockets = Vec::new();
+
         Self {
             listener_buckets,
             connection_buckets,
             udp_sockets,
+            raw_sockets,
         }
     }
 
     /// Inserts a TCP listener into the table.
     ///
-    /// If a socket with the same [`ListenerKey`] has already been inserted,
-    /// this method will return an error and the listener will not be inserted.
+    /// If a socket with the same [`ListenerKey`] has already been inserted and the two sockets
+    /// cannot be in the same `SO_REUSEPORT` group, this method will return an error and the
+    /// listener will not be inserted.
     pub(crate) fn insert_listener(
         &mut self,
         listener: Arc<TcpListenerBg<E>>,
     ) -> Result<(), Arc<TcpListenerBg<E>>> {
-        let key = listener.listener_key();
+        if self.has_conflicting_listener(listener.listener_key(), listener.reuse_port()) {
+            return Err(listener);
+        }
 
         let bucket = {
-            let hash = key.hash();
+            let hash = listener.listener_key().hash();
             let bucket_index = hash & LISTENER_BUCKET_MASK;
             &mut self.listener_buckets[bucket_index as usize]
         };
 
-        if bucket
-            .listeners
-            .iter()
-            .any(|tcp_listener| tcp_listener.listener_key() == listener.listener_key())
-        {
-            return Err(listener);
-        }
-
         bucket.listeners.push(listener);
         Ok(())
     }
@@ -236,7 +249,48 @@ impl<E: Ext> SocketTable<E> {
         self.udp_sockets.push(udp_socket);
     }
 
-    pub(crate) fn lookup_listener(&self, key: &ListenerKey) -> Option<&Arc<TcpListenerBg<E>>> {
+    pub(crate) fn insert_raw_socket(&mut self, raw_socket: Arc<RawIpSocketBg<E>>) {
+        debug_assert!(
+            !self
+                .raw_sockets
+                .iter()
+                .any(|socket| Arc::ptr_eq(socket, &raw_socket))
+        );
+        self.raw_sockets.push(raw_socket);
+    }
+
+    /// Returns whether a new listener with the key cannot be inserted because of the existing
+    /// listeners.
+    pub(crate) fn has_conflicting_listener(&self, key: &ListenerKey, reuse_port: bool) -> bool {
+        self.lookup_listeners(key)
+            .any(|listener| !reuse_port || !listener.reuse_port())
+    }
+
+    /// Looks up the listener that should handle a new connection.
+    ///
+    /// If multiple listeners form a `SO_REUSEPORT` group, one of them is selected by the hash of
+    /// the connection, so connections from the same flow always reach the same listener.
+    //
+    // TODO: Support selecting the listener by an attached BPF program
+    // (`SO_ATTACH_REUSEPORT_CBPF` and `SO_ATTACH_REUSEPORT_EBPF`).
+    pub(crate) fn lookup_listener(
+        &self,
+        key: &ListenerKey,
+        connection_key: &ConnectionKey,
+    ) -> Option<&Arc<TcpListenerBg<E>>> {
+        let nlisteners = self.lookup_listeners(key).count();
+        if nlisteners == 0 {
+            return None;
+        }
+
+        let index = connection_key.hash() as usize % nlisteners;
+        self.lookup_listeners(key).nth(index)
+    }
+
+    fn lookup_listeners<'a>(
+        &'a self,
+        key: &'a ListenerKey,
+    ) -> impl Iterator<Item = &'a Arc<TcpListenerBg<E>>> {
         let bucket = {
             let hash = key.hash();
             let bucket_index = hash & LISTENER_BUCKET_MASK;
@@ -246,7 +300,7 @@ impl<E: Ext> SocketTable<E> {
         bucket
             .listeners
             .iter()
-            .find(|listener| listener.listener_key() == key)
+            .filter(move |listener| listener.listener_key() == key)
     }
 
     pub(crate) fn lookup_connection(
@@ -265,9 +319,12 @@ impl<E: Ext> SocketTable<E> {
             .find(|connection| connection.connection_key() == key)
     }
 
-    pub(crate) fn remove_listener(&mut self, key: &ListenerKey) -> Option<Arc<TcpListenerBg<E>>> {
+    pub(crate) fn remove_listener(
+        &mut self,
+        listener: &Arc<TcpListenerBg<E>>,
+    ) -> Option<Arc<TcpListenerBg<E>>> {
         let bucket = {
-            let hash = key.hash();
+            let hash = listener.listener_key().hash();
             let bucket_index = hash & LISTENER_BUCKET_MASK;
             &mut self.listener_buckets[bucket_index as usize]
         };
@@ -275,7 +332,7 @@ impl<E: Ext> SocketTable<E> {
         let index = bucket
             .listeners
             .iter()
-            .position(|tcp_listener| tcp_listener.listener_key() == key)?;
+            .position(|tcp_listener| Arc::ptr_eq(tcp_listener, listener))?;
         Some(bucket.listeners.swap_remove(index))
     }
 
@@ -314,6 +371,45 @@ impl<E: Ext> SocketTable<E> {
     pub(crate) fn udp_socket_iter(&self) -> impl Iterator<Item = &Arc<UdpSocketBg<E>>> {
         self.udp_sockets.iter()
     }
+
+    /// Looks up the UDP socket that should handle a unicast datagram of the flow, which consists
+    /// of the local endpoint and the remote endpoint.
+    ///
+    /// If multiple UDP sockets bound to the local endpoint form a `SO_REUSEPORT` group, one of them
+    /// is selected by the hash of the flow, so datagrams from the same flow always reach the same
+    /// socket. If there is no such group, this method returns `None`.
+    //
+    // TODO: Support selecting the socket by an attached BPF program
+    // (`SO_ATTACH_REUSEPORT_CBPF` and `SO_ATTACH_REUSEPORT_EBPF`).
+    pub(crate) fn lookup_reuse_port_udp_socket(
+        &self,
+        flow: &(IpEndpoint, IpEndpoint),
+    ) -> Option<&Arc<UdpSocketBg<E>>> {
+        let in_group = |socket: &&Arc<UdpSocketBg<E>>| socket.is_in_reuse_port_group(&flow.0);
+
+        let nsockets = self.udp_sockets.iter().filter(in_group).count();
+        if nsockets == 0 {
+            return None;
+        }
+
+        let index = ConnectionKey::from(*flow).hash() as usize % nsockets;
+        self.udp_sockets.iter().filter(in_group).nth(index)
+    }
+
+    pub(crate) fn remove_raw_socket(
+        &mut self,
+        socket: &Arc<RawIpSocketBg<E>>,
+    ) -> Option<Arc<RawIpSocketBg<E>>> {
+        let index = self
+            .raw_sockets
+            .iter()
+            .position(|raw_socket| Arc::ptr_eq(raw_socket, socket))?;
+        Some(self.raw_sockets.swap_remove(index))
+    }
+
+    pub(crate) fn raw_socket_iter(&self) -> impl Iterator<Item = &Arc<RawIpSocketBg<E>>> {
+        self.raw_sockets.iter()
+    }
 }
 
 impl<E: Ext> Default for SocketTable<E> {
@@ -1,3 +1,11 @@
 // SPDX-License-Identifier: MPL-2.0
 
 pub use smoltcp::time::{Duration, Instant};
+
+/// A clock that reads the wall-clock time.
+///
+/// The time is used to timestamp the packets when they arrive at the sockets.
+pub trait WallClock {
+    /// Returns the current time as the duration since the Unix epoch.
+    fn now() -> core::time::Duration;
+}
@@ -1,6 +1,9 @@
 // SPDX-License-Identifier: MPL-2.0
 
 pub mod cpu;
+pub mod cpufreq;
+pub mod kexec;
+pub mod pmu;
 mod power;
 pub mod signal;
 
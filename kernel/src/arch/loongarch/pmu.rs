@@ -0,0 +1,37 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The hardware counters of perf events.
+//!
+//! TODO: Support the hardware performance counters on LoongArch.
+
+use ostd::irq::DisabledLocalIrqGuard;
+
+use crate::perf::HwEvent;
+
+/// Returns whether the hardware event can be counted.
+pub fn is_event_supported(_hw_event: HwEvent) -> bool {
+    false
+}
+
+/// A hardware counter that counts a hardware event on the current CPU.
+///
+/// No hardware counters can be created because they are not supported yet.
+pub enum HwCounter {}
+
+impl HwCounter {
+    /// Starts counting the hardware event on the current CPU.
+    pub fn start(
+        _hw_event: HwEvent,
+        _count_user: bool,
+        _count_kernel: bool,
+        _irq_guard: &DisabledLocalIrqGuard,
+    ) -> Option<Self> {
+        None
+    }
+
+    /// Returns the number of the events counted since the last call, or since the counter is
+    /// started.
+    pub fn take_count(&mut self, _irq_guard: &DisabledLocalIrqGuard) -> u64 {
+        match *self {}
+    }
+}
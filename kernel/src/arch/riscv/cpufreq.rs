@@ -0,0 +1,14 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The CPU frequency scaling driver.
+//!
+//! TODO: Support scaling the CPU frequency on RISC-V.
+
+use crate::cpu::freq::CpufreqDriver;
+
+/// Returns the CPU frequency scaling driver.
+///
+/// This method always returns `None` because the CPU frequency cannot be scaled yet.
+pub fn driver() -> Option<&'static dyn CpufreqDriver> {
+    None
+}
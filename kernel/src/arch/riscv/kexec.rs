@@ -0,0 +1,37 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The loader of the kernel images for kexec.
+//!
+//! Kexec is not supported on RISC-V, where the syscalls fail with `ENOSYS` before
+//! loading any images. The loaders are kept so that the syscalls build on all architectures.
+//!
+//! TODO: Support loading kernel images on RISC-V.
+
+use ostd::power::KexecImage;
+
+use crate::prelude::*;
+
+/// The architecture in the flags of `kexec_load`, which is `EM_RISCV`.
+pub const KEXEC_ARCH: u32 = 243 << 16;
+
+/// Loads a kernel image with the initrd and the command line.
+///
+/// This method always fails because no kernel image formats are supported yet.
+pub fn load_kernel_image(
+    _kernel: &[u8],
+    _initrd: Option<&[u8]>,
+    _cmdline: &[u8],
+) -> Result<KexecImage> {
+    return_errno_with_message!(Errno::ENOEXEC, "the kernel image format is not supported");
+}
+
+/// Loads a crash kernel image with the initrd and the command line.
+///
+/// This method always fails because no kernel image formats are supported yet.
+pub fn load_crash_kernel_image(
+    _kernel: &[u8],
+    _initrd: Option<&[u8]>,
+    _cmdline: &[u8],
+) -> Result<KexecImage> {
+    return_errno_with_message!(Errno::ENOEXEC, "the kernel image format is not supported");
+}
@@ -0,0 +1,58 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The CPU frequency scaling driver backed by the P-states of Intel CPUs.
+
+use ostd::{
+    arch::cpu::pstate::{self, BUS_FREQ_KHZ, PstateInfo},
+    irq::DisabledLocalIrqGuard,
+};
+use spin::Once;
+
+use crate::cpu::freq::CpufreqDriver;
+
+/// Returns the CPU frequency scaling driver.
+///
+/// This method will return `None` if the CPU frequency cannot be scaled.
+pub fn driver() -> Option<&'static dyn CpufreqDriver> {
+    static DRIVER: Once<Option<IntelCpufreq>> = Once::new();
+
+    DRIVER
+        .call_once(|| pstate::pstate_info().map(|info| IntelCpufreq { info }))
+        .as_ref()
+        .map(|driver| driver as _)
+}
+
+/// The driver that requests the P-states via the `IA32_PERF_CTL` MSR.
+///
+/// This is similar to the `intel_cpufreq` driver (i.e., the `intel_pstate` driver in the passive
+/// mode) in Linux, which leaves the frequency selection to the generic governors.
+struct IntelCpufreq {
+    info: &'static PstateInfo,
+}
+
+impl CpufreqDriver for IntelCpufreq {
+    fn name(&self) -> &'static str {
+        "intel_cpufreq"
+    }
+
+    fn min_freq(&self) -> u32 {
+        self.info.min_ratio() as u32 * BUS_FREQ_KHZ
+    }
+
+    fn max_freq(&self) -> u32 {
+        self.info.turbo_ratio() as u32 * BUS_FREQ_KHZ
+    }
+
+    fn current_freq(&self, irq_guard: &DisabledLocalIrqGuard) -> u32 {
+        pstate::current_ratio(irq_guard) as u32 * BUS_FREQ_KHZ
+    }
+
+    fn set_freq(&self, freq: u32, irq_guard: &DisabledLocalIrqGuard) -> u32 {
+        let ratio = freq
+            .div_ceil(BUS_FREQ_KHZ)
+            .clamp(self.info.min_ratio() as u32, self.info.turbo_ratio() as u32);
+        pstate::set_ratio(ratio as u8, irq_guard);
+
+        ratio * BUS_FREQ_KHZ
+    }
+}
@@ -0,0 +1,485 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The loader of the kernel images for kexec.
+//!
+//! Only the bzImage format of Linux is supported. The protected-mode kernel is loaded at its
+//! preferred address, the boot parameters (i.e., the "zero page") are built from the setup header
+//! of the image, and the new kernel is entered via its 64-bit entry.
+//!
+//! The crash kernel is loaded in the memory reserved for it instead, so it must be relocatable.
+//! The memory of the current kernel is described by an ELF core header passed to the crash kernel
+//! (see [`crate::power::vmcore`]).
+//!
+//! Reference: <https://docs.kernel.org/arch/x86/boot.html>
+
+use core::ops::Range;
+
+use align_ext::AlignExt;
+use ostd::{
+    boot::memory_region::{MemoryRegion, MemoryRegionType},
+    mm::{Paddr, VmIo},
+    power::KexecImage,
+};
+
+use crate::{power::vmcore::new_elf_core_header, prelude::*};
+
+/// The architecture in the flags of `kexec_load`, which is `EM_X86_64`.
+pub const KEXEC_ARCH: u32 = 62 << 16;
+
+// The offsets of the fields in the setup header, which also apply to the boot parameters.
+const SETUP_SECTS: usize = 0x1f1;
+const JUMP: usize = 0x200;
+const HEADER: usize = 0x202;
+const VERSION: usize = 0x206;
+const TYPE_OF_LOADER: usize = 0x210;
+const CODE32_START: usize = 0x214;
+const RAMDISK_IMAGE: usize = 0x218;
+const RAMDISK_SIZE: usize = 0x21c;
+const CMD_LINE_PTR: usize = 0x228;
+const INITRD_ADDR_MAX: usize = 0x22c;
+const KERNEL_ALIGNMENT: usize = 0x230;
+const RELOCATABLE_KERNEL: usize = 0x234;
+const XLOADFLAGS: usize = 0x236;
+const CMDLINE_SIZE: usize = 0x238;
+const PREF_ADDRESS: usize = 0x258;
+const INIT_SIZE: usize = 0x260;
+/// The end of the fields that are read from the setup header.
+const HEADER_FIELDS_END: usize = 0x264;
+
+// The offsets of the fields in the boot parameters that are outside the setup header.
+const EXT_RAMDISK_IMAGE: usize = 0x0c0;
+const EXT_RAMDISK_SIZE: usize = 0x0c4;
+const EXT_CMD_LINE_PTR: usize = 0x0c8;
+const E820_ENTRIES: usize = 0x1e8;
+const E820_TABLE: usize = 0x2d0;
+
+/// The magic number of the setup header, i.e., `"HdrS"`.
+const HEADER_MAGIC: u32 = 0x5372_6448;
+/// The minimum version of the boot protocol, which is the first version with `XLOADFLAGS`.
+const MIN_VERSION: u16 = 0x020c;
+/// The bit in `XLOADFLAGS` that indicates the kernel has the 64-bit entry.
+const XLF_KERNEL_64: u16 = 1 << 0;
+/// The type of the boot loader that is not assigned an ID.
+const LOADER_TYPE_UNDEFINED: u8 = 0xff;
+/// The offset of the 64-bit entry from the start of the protected-mode kernel.
+const ENTRY_64_OFFSET: usize = 0x200;
+
+const E820_MAX_ENTRIES: usize = 128;
+const E820_ENTRY_SIZE: usize = 20;
+const E820_TYPE_RAM: u32 = 1;
+const E820_TYPE_RESERVED: u32 = 2;
+const E820_TYPE_NVS: u32 = 4;
+const E820_TYPE_UNUSABLE: u32 = 5;
+
+/// The lowest address to place the boot parameters, the command line, and the initrd.
+const MIN_PLACEMENT_ADDR: Paddr = 0x10_0000;
+/// The highest address to place the boot parameters and the command line.
+const MAX_PLACEMENT_ADDR: Paddr = 0x1_0000_0000;
+
+/// Loads a kernel image with the initrd and the command line.
+///
+/// The command line must include the trailing NUL byte.
+pub fn load_kernel_image(
+    kernel: &[u8],
+    initrd: Option<&[u8]>,
+    cmdline: &[u8],
+) -> Result<KexecImage> {
+    load_image(kernel, initrd, cmdline, None)
+}
+
+/// Loads a crash kernel image with the initrd and the command line.
+///
+/// The image is loaded in the memory reserved for the crash kernel. The command line must include
+/// the trailing NUL byte.
+pub fn load_crash_kernel_image(
+    kernel: &[u8],
+    initrd: Option<&[u8]>,
+    cmdline: &[u8],
+) -> Result<KexecImage> {
+    let crash_region = ostd::power::crash_kernel_region().ok_or_else(|| {
+        Error::with_message(
+            Errno::EADDRNOTAVAIL,
+            "no memory is reserved for the crash kernel",
+        )
+    })?;
+
+    load_image(kernel, initrd, cmdline, Some(crash_region))
+}
+
+fn load_image(
+    kernel: &[u8],
+    initrd: Option<&[u8]>,
+    cmdline: &[u8],
+    crash_region: Option<Range<Paddr>>,
+) -> Result<KexecImage> {
+    let initrd = initrd.filter(|initrd| !initrd.is_empty());
+
+    if kernel.len() < HEADER_FIELDS_END
+        || read_u32(kernel, HEADER) != HEADER_MAGIC
+        || read_u16(kernel, VERSION) < MIN_VERSION
+    {
+        return_errno_with_message!(Errno::ENOEXEC, "the kernel image is not a bzImage");
+    }
+    if read_u16(kernel, XLOADFLAGS) & XLF_KERNEL_64 == 0 {
+        return_errno_with_message!(Errno::ENOEXEC, "the kernel image has no 64-bit entry");
+    }
+
+    let setup_sects = match kernel[SETUP_SECTS] {
+        0 => 4,
+        sects => sects as usize,
+    };
+    let setup_size = (setup_sects + 1) * 512;
+    if setup_size >= kernel.len() {
+        return_errno_with_message!(Errno::ENOEXEC, "the kernel image is truncated");
+    }
+    let pmode_kernel = &kernel[setup_size..];
+
+    let kernel_size = (read_u32(kernel, INIT_SIZE) as usize)
+        .max(pmode_kernel.len())
+        .align_up(PAGE_SIZE);
+    let kernel_addr = match crash_region.as_ref() {
+        // Load the kernel at its preferred address, so that it does not need to be relocated.
+        None => {
+            let pref_addr = read_u64(kernel, PREF_ADDRESS) as Paddr;
+            if pref_addr % PAGE_SIZE != 0 {
+                return_errno_with_message!(
+                    Errno::ENOEXEC,
+                    "the preferred address is not page-aligned"
+                );
+            }
+            pref_addr
+        }
+        // Load the crash kernel at the start of the reserved memory, so that the rest of the
+        // memory can be used by the crash kernel.
+        Some(crash_region) => {
+            if kernel[RELOCATABLE_KERNEL] == 0 {
+                return_errno_with_message!(Errno::ENOEXEC, "the crash kernel is not relocatable");
+            }
+            let align = (read_u32(kernel, KERNEL_ALIGNMENT) as usize).max(PAGE_SIZE);
+            if !align.is_power_of_two() {
+                return_errno_with_message!(Errno::ENOEXEC, "the kernel alignment is invalid");
+            }
+            crash_region.start.align_up(align)
+        }
+    };
+    // The memory is reserved for the whole `init_size`, which is required to decompress the
+    // kernel.
+    let kernel_dest = kernel_addr..kernel_addr + kernel_size;
+    let mut dests = vec![kernel_dest];
+
+    let regions = placement_regions(crash_region.as_ref());
+    let find_dest = |size: usize, limit: Paddr, dests: &[Range<Paddr>], what: &'static str| {
+        find_free_range(size.align_up(PAGE_SIZE), limit, &regions, dests)
+            .ok_or_else(|| Error::with_message(Errno::ENOMEM, what))
+    };
+
+    // Describe the memory of the current kernel for the crash kernel, and pass the ELF core
+    // header with the command line.
+    let mut cmdline = cmdline.to_vec();
+    let elfcorehdr = if let Some(crash_region) = crash_region.as_ref() {
+        let header = new_elf_core_header(&old_memory_ranges(crash_region));
+        let header_dest = find_dest(
+            header.len(),
+            MAX_PLACEMENT_ADDR,
+            &dests,
+            "no memory for the ELF core header",
+        )?;
+
+        cmdline.pop();
+        cmdline.extend_from_slice(format!(" elfcorehdr={:#x}\0", header_dest.start).as_bytes());
+        dests.push(header_dest);
+        Some(header)
+    } else {
+        None
+    };
+
+    if cmdline.len() > read_u32(kernel, CMDLINE_SIZE) as usize + 1 {
+        return_errno_with_message!(Errno::EINVAL, "the command line is too long");
+    }
+
+    // The boot parameters take the first page, which is followed by the command line.
+    let params_dest = find_dest(
+        PAGE_SIZE + cmdline.len(),
+        MAX_PLACEMENT_ADDR,
+        &dests,
+        "no memory for the boot parameters",
+    )?;
+    dests.push(params_dest.clone());
+
+    let initrd_dest = if let Some(initrd) = initrd {
+        let initrd_max = read_u32(kernel, INITRD_ADDR_MAX) as Paddr + 1;
+        let initrd_dest = find_dest(initrd.len(), initrd_max, &dests, "no memory for the initrd")?;
+        dests.push(initrd_dest.clone());
+        Some(initrd_dest)
+    } else {
+        None
+    };
+
+    let e820_table = match crash_region.as_ref() {
+        None => new_e820_table(),
+        Some(crash_region) => new_crash_e820_table(crash_region, &dests[1]),
+    };
+    let params = new_boot_params(
+        kernel,
+        kernel_addr,
+        params_dest.start,
+        initrd_dest
+            .map(|dest| dest.start)
+            .zip(initrd.map(<[u8]>::len)),
+        &e820_table,
+    );
+
+    let entry = kernel_addr + ENTRY_64_OFFSET;
+    let image = if crash_region.is_some() {
+        KexecImage::new_crash(&dests, entry, params_dest.start)?
+    } else {
+        KexecImage::new(&dests, entry, params_dest.start)?
+    };
+    let mut segments = image.segments().iter();
+    segments
+        .next()
+        .unwrap()
+        .frames()
+        .write_bytes(0, pmode_kernel)?;
+    if let Some(header) = elfcorehdr {
+        segments.next().unwrap().frames().write_bytes(0, &header)?;
+    }
+    let params_segment = segments.next().unwrap();
+    params_segment.frames().write_bytes(0, &params)?;
+    params_segment.frames().write_bytes(PAGE_SIZE, &cmdline)?;
+    if let Some(initrd) = initrd {
+        segments.next().unwrap().frames().write_bytes(0, initrd)?;
+    }
+
+    Ok(image)
+}
+
+/// Creates the boot parameters for the kernel.
+///
+/// The command line is placed right after the boot parameters.
+fn new_boot_params(
+    kernel: &[u8],
+    kernel_addr: Paddr,
+    params_addr: Paddr,
+    initrd: Option<(Paddr, usize)>,
+    e820_table: &[(Range<Paddr>, u32)],
+) -> Vec<u8> {
+    let mut params = vec![0u8; PAGE_SIZE];
+
+    let header_end = (HEADER + kernel[JUMP + 1] as usize).min(kernel.len());
+    params[SETUP_SECTS..header_end].copy_from_slice(&kernel[SETUP_SECTS..header_end]);
+
+    params[TYPE_OF_LOADER] = LOADER_TYPE_UNDEFINED;
+    write_u32(&mut params, CODE32_START, kernel_addr as u32);
+
+    let cmdline_addr = (params_addr + PAGE_SIZE) as u64;
+    write_u32(&mut params, CMD_LINE_PTR, cmdline_addr as u32);
+    write_u32(&mut params, EXT_CMD_LINE_PTR, (cmdline_addr >> 32) as u32);
+
+    if let Some((initrd_addr, initrd_size)) = initrd {
+        let (initrd_addr, initrd_size) = (initrd_addr as u64, initrd_size as u64);
+        write_u32(&mut params, RAMDISK_IMAGE, initrd_addr as u32);
+        write_u32(&mut params, EXT_RAMDISK_IMAGE, (initrd_addr >> 32) as u32);
+        write_u32(&mut params, RAMDISK_SIZE, initrd_size as u32);
+        write_u32(&mut params, EXT_RAMDISK_SIZE, (initrd_size >> 32) as u32);
+    }
+
+    // TODO: Pass the ACPI RSDP address and the EFI information to the new kernel. Currently, the
+    // new kernel has to find the RSDP in the legacy BIOS areas.
+    params[E820_ENTRIES] = e820_table.len() as u8;
+    for (index, (range, typ)) in e820_table.iter().enumerate() {
+        let offset = E820_TABLE + index * E820_ENTRY_SIZE;
+        write_u64(&mut params, offset, range.start as u64);
+        write_u64(&mut params, offset + 8, (range.end - range.start) as u64);
+        write_u32(&mut params, offset + 16, *typ);
+    }
+
+    params
+}
+
+/// Creates the E820 memory map from the memory regions.
+///
+/// The adjacent regions of the same type are merged, and the regions beyond the capacity of the
+/// boot parameters are dropped.
+fn new_e820_table() -> Vec<(Range<Paddr>, u32)> {
+    let regions = ostd::boot::boot_info()
+        .memory_regions
+        .iter()
+        .filter_map(|region| Some((region.base()..region.end(), e820_type(region)?)))
+        .collect::<Vec<_>>();
+
+    merge_e820_regions(regions)
+}
+
+/// Creates the E820 memory map for the crash kernel.
+///
+/// Only the memory reserved for the crash kernel is RAM, except for the ELF core header. The RAM
+/// of the current kernel is reserved, so that the crash kernel does not overwrite it and can read
+/// it as the old memory.
+fn new_crash_e820_table(
+    crash_region: &Range<Paddr>,
+    elfcorehdr: &Range<Paddr>,
+) -> Vec<(Range<Paddr>, u32)> {
+    let mut regions = Vec::new();
+    for region in ostd::boot::boot_info().memory_regions.iter() {
+        let Some(typ) = e820_type(region) else {
+            continue;
+        };
+        let typ = if typ == E820_TYPE_RAM {
+            E820_TYPE_RESERVED
+        } else {
+            typ
+        };
+        for range in range_difference(&(region.base()..region.end()), crash_region) {
+            regions.push((range, typ));
+        }
+    }
+
+    for range in range_difference(crash_region, elfcorehdr) {
+        regions.push((range, E820_TYPE_RAM));
+    }
+    regions.push((elfcorehdr.clone(), E820_TYPE_RESERVED));
+
+    merge_e820_regions(regions)
+}
+
+/// Returns the RAM of the current kernel, which is dumped by the crash kernel.
+fn old_memory_ranges(crash_region: &Range<Paddr>) -> Vec<Range<Paddr>> {
+    let mut ranges = ostd::boot::boot_info()
+        .memory_regions
+        .iter()
+        .filter(|region| e820_type(region) == Some(E820_TYPE_RAM))
+        .flat_map(|region| range_difference(&(region.base()..region.end()), crash_region))
+        .collect::<Vec<_>>();
+    ranges.sort_unstable_by_key(|range| range.start);
+
+    let mut merged: Vec<Range<Paddr>> = Vec::new();
+    for range in ranges {
+        if let Some(last) = merged.last_mut()
+            && last.end == range.start
+        {
+            last.end = range.end;
+            continue;
+        }
+        merged.push(range);
+    }
+
+    merged
+}
+
+fn merge_e820_regions(mut regions: Vec<(Range<Paddr>, u32)>) -> Vec<(Range<Paddr>, u32)> {
+    regions.sort_unstable_by_key(|(range, _)| range.start);
+
+    let mut table: Vec<(Range<Paddr>, u32)> = Vec::new();
+    for (range, typ) in regions {
+        if let Some((last_range, last_typ)) = table.last_mut()
+            && *last_typ == typ
+            && last_range.end == range.start
+        {
+            last_range.end = range.end;
+            continue;
+        }
+        table.push((range, typ));
+    }
+    table.truncate(E820_MAX_ENTRIES);
+
+    table
+}
+
+fn e820_type(region: &MemoryRegion) -> Option<u32> {
+    let typ = match region.typ() {
+        MemoryRegionType::Usable
+        | MemoryRegionType::Reclaimable
+        | MemoryRegionType::Kernel
+        | MemoryRegionType::Module => E820_TYPE_RAM,
+        MemoryRegionType::Reserved | MemoryRegionType::Framebuffer => E820_TYPE_RESERVED,
+        MemoryRegionType::NonVolatileSleep => E820_TYPE_NVS,
+        MemoryRegionType::BadMemory => E820_TYPE_UNUSABLE,
+        MemoryRegionType::Unknown => return None,
+    };
+    Some(typ)
+}
+
+/// Returns the page-aligned memory regions where the segments can be placed.
+///
+/// The segments of the crash kernel must be placed in the memory reserved for it. Otherwise, the
+/// segments can be placed in any usable memory.
+fn placement_regions(crash_region: Option<&Range<Paddr>>) -> Vec<Range<Paddr>> {
+    if let Some(crash_region) = crash_region {
+        return vec![crash_region.clone()];
+    }
+
+    let mut regions = ostd::boot::boot_info()
+        .memory_regions
+        .iter()
+        .filter(|region| region.typ() == MemoryRegionType::Usable)
+        .map(|region| region.base().align_up(PAGE_SIZE)..region.end().align_down(PAGE_SIZE))
+        .collect::<Vec<_>>();
+    regions.sort_unstable_by_key(|region| core::cmp::Reverse(region.end));
+
+    regions
+}
+
+/// Finds a page-aligned range of `size` bytes in the regions below `limit`, which does not overlap
+/// with the used ranges.
+///
+/// Like Linux, the range is searched from the top of the memory, so that the low memory is left
+/// for the new kernel. The regions must be sorted in descending order.
+fn find_free_range(
+    size: usize,
+    limit: Paddr,
+    regions: &[Range<Paddr>],
+    used: &[Range<Paddr>],
+) -> Option<Range<Paddr>> {
+    for region in regions {
+        let mut end = region.end.min(limit.align_down(PAGE_SIZE));
+        while let Some(start) = end.checked_sub(size) {
+            if start < region.start.max(MIN_PLACEMENT_ADDR) {
+                break;
+            }
+
+            match used
+                .iter()
+                .find(|range| range.start < end && start < range.end)
+            {
+                Some(range) => end = range.start.align_down(PAGE_SIZE),
+                None => return Some(start..end),
+            }
+        }
+    }
+
+    None
+}
+
+/// Returns the parts of `range` that do not overlap with `other`.
+fn range_difference(
+    range: &Range<Paddr>,
+    other: &Range<Paddr>,
+) -> impl Iterator<Item = Range<Paddr>> {
+    [
+        range.start..range.end.min(other.start),
+        range.start.max(other.end)..range.end,
+    ]
+    .into_iter()
+    .filter(|range| !range.is_empty())
+}
+
+fn read_u16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(buf[offset..offset + 2].try_into().unwrap())
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(buf: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(buf[offset..offset + 8].try_into().unwrap())
+}
+
+fn write_u32(buf: &mut [u8], offset: usize, value: u32) {
+    buf[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn write_u64(buf: &mut [u8], offset: usize, value: u64) {
+    buf[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
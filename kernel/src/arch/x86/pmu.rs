@@ -0,0 +1,78 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The hardware counters of perf events, which are backed by the architectural PMU.
+
+use ostd::{
+    arch::cpu::pmu::{ArchPerfEvent, PerfCounter, pmu_info},
+    irq::DisabledLocalIrqGuard,
+};
+
+use crate::perf::HwEvent;
+
+/// Returns the architectural event that corresponds to the hardware event.
+fn arch_event(hw_event: HwEvent) -> Option<ArchPerfEvent> {
+    let arch_event = match hw_event {
+        HwEvent::CpuCycles => ArchPerfEvent::CoreCycles,
+        HwEvent::Instructions => ArchPerfEvent::InstructionsRetired,
+        HwEvent::CacheReferences => ArchPerfEvent::LlcReferences,
+        HwEvent::CacheMisses => ArchPerfEvent::LlcMisses,
+        HwEvent::BranchInstructions => ArchPerfEvent::BranchInstructionsRetired,
+        HwEvent::BranchMisses => ArchPerfEvent::BranchMissesRetired,
+        HwEvent::RefCpuCycles => ArchPerfEvent::ReferenceCycles,
+        HwEvent::BusCycles | HwEvent::StalledCyclesFrontend | HwEvent::StalledCyclesBackend => {
+            return None;
+        }
+    };
+    Some(arch_event)
+}
+
+/// Returns whether the hardware event can be counted.
+pub fn is_event_supported(hw_event: HwEvent) -> bool {
+    let Some(pmu_info) = pmu_info() else {
+        return false;
+    };
+    arch_event(hw_event).is_some_and(|arch_event| pmu_info.is_available(arch_event))
+}
+
+/// A hardware counter that counts a hardware event on the current CPU.
+pub struct HwCounter {
+    counter: PerfCounter,
+    last_value: u64,
+}
+
+impl HwCounter {
+    /// Starts counting the hardware event on the current CPU.
+    ///
+    /// This method will return `None` if there are no free hardware counters.
+    pub fn start(
+        hw_event: HwEvent,
+        count_user: bool,
+        count_kernel: bool,
+        irq_guard: &DisabledLocalIrqGuard,
+    ) -> Option<Self> {
+        let arch_event = arch_event(hw_event)?;
+        let counter = PerfCounter::start(arch_event, count_user, count_kernel, irq_guard)?;
+        let last_value = counter.read(irq_guard);
+
+        Some(Self {
+            counter,
+            last_value,
+        })
+    }
+
+    /// Returns the number of the events counted since the last call, or since the counter is
+    /// started.
+    pub fn take_count(&mut self, irq_guard: &DisabledLocalIrqGuard) -> u64 {
+        let width = pmu_info().unwrap().counter_width();
+        let mask = if width >= 64 {
+            u64::MAX
+        } else {
+            (1 << width) - 1
+        };
+
+        let value = self.counter.read(irq_guard);
+        let count = value.wrapping_sub(self.last_value) & mask;
+        self.last_value = value;
+        count
+    }
+}
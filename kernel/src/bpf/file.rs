@@ -0,0 +1,151 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The files of eBPF maps and programs, which are returned by the `bpf` system call.
+
+use core::fmt::Display;
+
+use super::{map::BpfMap, prog::BpfProg};
+use crate::{
+    events::IoEvents,
+    fs::{
+        file::{CreationFlags, FileLike, Mappable, file_table::FdFlags},
+        pseudofs::AnonInodeFs,
+        vfs::path::Path,
+    },
+    prelude::*,
+    process::signal::{PollHandle, Pollable},
+};
+
+/// The file of an eBPF map.
+pub struct BpfMapFile {
+    map: Arc<dyn BpfMap>,
+    pseudo_path: Path,
+}
+
+impl BpfMapFile {
+    pub fn new(map: Arc<dyn BpfMap>) -> Self {
+        Self {
+            map,
+            pseudo_path: AnonInodeFs::new_path(|_| "anon_inode:bpf-map".to_string()),
+        }
+    }
+
+    /// Returns the map.
+    pub fn map(&self) -> &Arc<dyn BpfMap> {
+        &self.map
+    }
+}
+
+impl Pollable for BpfMapFile {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        match self.map.as_ringbuf() {
+            Some(ringbuf) => ringbuf.poll(mask, poller),
+            None => (IoEvents::IN | IoEvents::OUT) & mask,
+        }
+    }
+}
+
+impl FileLike for BpfMapFile {
+    fn mappable(&self) -> Result<Mappable> {
+        match self.map.as_ringbuf() {
+            Some(ringbuf) => Ok(Mappable::Vmo(ringbuf.vmo().clone())),
+            None => return_errno_with_message!(Errno::ENODEV, "the map is not mappable"),
+        }
+    }
+
+    fn path(&self) -> &Path {
+        &self.pseudo_path
+    }
+
+    fn dump_proc_fdinfo(self: Arc<Self>, fd_flags: FdFlags) -> Box<dyn Display> {
+        struct FdInfo {
+            inner: Arc<BpfMapFile>,
+            fd_flags: FdFlags,
+        }
+
+        impl Display for FdInfo {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                let mut flags = self.inner.status_flags().bits() | self.inner.access_mode() as u32;
+                if self.fd_flags.contains(FdFlags::CLOEXEC) {
+                    flags |= CreationFlags::O_CLOEXEC.bits();
+                }
+
+                let attr = self.inner.map.attr();
+                writeln!(f, "pos:\t{}", 0)?;
+                writeln!(f, "flags:\t0{:o}", flags)?;
+                writeln!(f, "mnt_id:\t{}", AnonInodeFs::mount_node().id())?;
+                writeln!(f, "ino:\t{}", AnonInodeFs::shared_inode().ino())?;
+                writeln!(f, "map_type:\t{}", attr.map_type as u32)?;
+                writeln!(f, "key_size:\t{}", attr.key_size)?;
+                writeln!(f, "value_size:\t{}", attr.value_size)?;
+                writeln!(f, "max_entries:\t{}", attr.max_entries)?;
+                writeln!(f, "map_flags:\t{:#x}", attr.map_flags)
+            }
+        }
+
+        Box::new(FdInfo {
+            inner: self,
+            fd_flags,
+        })
+    }
+}
+
+/// The file of an eBPF program.
+pub struct BpfProgFile {
+    prog: Arc<BpfProg>,
+    pseudo_path: Path,
+}
+
+impl BpfProgFile {
+    pub fn new(prog: Arc<BpfProg>) -> Self {
+        Self {
+            prog,
+            pseudo_path: AnonInodeFs::new_path(|_| "anon_inode:bpf-prog".to_string()),
+        }
+    }
+
+    /// Returns the program.
+    pub fn prog(&self) -> &Arc<BpfProg> {
+        &self.prog
+    }
+}
+
+impl Pollable for BpfProgFile {
+    fn poll(&self, mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
+        (IoEvents::IN | IoEvents::OUT) & mask
+    }
+}
+
+impl FileLike for BpfProgFile {
+    fn path(&self) -> &Path {
+        &self.pseudo_path
+    }
+
+    fn dump_proc_fdinfo(self: Arc<Self>, fd_flags: FdFlags) -> Box<dyn Display> {
+        struct FdInfo {
+            inner: Arc<BpfProgFile>,
+            fd_flags: FdFlags,
+        }
+
+        impl Display for FdInfo {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                let mut flags = self.inner.status_flags().bits() | self.inner.access_mode() as u32;
+                if self.fd_flags.contains(FdFlags::CLOEXEC) {
+                    flags |= CreationFlags::O_CLOEXEC.bits();
+                }
+
+                writeln!(f, "pos:\t{}", 0)?;
+                writeln!(f, "flags:\t0{:o}", flags)?;
+                writeln!(f, "mnt_id:\t{}", AnonInodeFs::mount_node().id())?;
+                writeln!(f, "ino:\t{}", AnonInodeFs::shared_inode().ino())?;
+                writeln!(f, "prog_type:\t{}", self.inner.prog.prog_type() as u32)?;
+                writeln!(f, "prog_jited:\t{}", 0)
+            }
+        }
+
+        Box::new(FdInfo {
+            inner: self,
+            fd_flags,
+        })
+    }
+}
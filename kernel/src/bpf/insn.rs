@@ -0,0 +1,128 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The eBPF instruction set.
+//!
+//! Reference: <https://docs.kernel.org/bpf/standardization/instruction-set.html>.
+
+/// An eBPF instruction.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/bpf.h#L71>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub struct BpfInsn {
+    pub(super) code: u8,
+    /// The destination register in the low 4 bits and the source register in the high 4 bits.
+    regs: u8,
+    pub(super) off: i16,
+    pub(super) imm: i32,
+}
+
+impl BpfInsn {
+    pub(super) fn class(&self) -> u8 {
+        self.code & 0x07
+    }
+
+    pub(super) fn dst(&self) -> usize {
+        (self.regs & 0x0f) as usize
+    }
+
+    pub(super) fn src(&self) -> usize {
+        (self.regs >> 4) as usize
+    }
+
+    /// Returns whether the instruction is the first half of a 64-bit immediate load.
+    pub(super) fn is_ld_imm64(&self) -> bool {
+        self.code == BPF_LD | BPF_IMM | BPF_DW
+    }
+}
+
+/// The maximum number of instructions in a program.
+pub const BPF_MAXINSNS: usize = 4096;
+
+/// The number of registers, including the read-only frame pointer.
+pub(super) const MAX_BPF_REG: usize = 11;
+/// The frame pointer.
+pub(super) const BPF_REG_FP: usize = 10;
+
+/// The size of the stack in bytes.
+pub(super) const MAX_BPF_STACK: usize = 512;
+
+// Instruction classes
+pub(super) const BPF_LD: u8 = 0x00;
+pub(super) const BPF_LDX: u8 = 0x01;
+pub(super) const BPF_ST: u8 = 0x02;
+pub(super) const BPF_STX: u8 = 0x03;
+pub(super) const BPF_ALU: u8 = 0x04;
+pub(super) const BPF_JMP: u8 = 0x05;
+pub(super) const BPF_JMP32: u8 = 0x06;
+pub(super) const BPF_ALU64: u8 = 0x07;
+
+// Load and store sizes
+pub(super) const BPF_W: u8 = 0x00;
+pub(super) const BPF_H: u8 = 0x08;
+pub(super) const BPF_B: u8 = 0x10;
+pub(super) const BPF_DW: u8 = 0x18;
+
+// Load and store modes
+pub(super) const BPF_IMM: u8 = 0x00;
+pub(super) const BPF_ABS: u8 = 0x20;
+pub(super) const BPF_IND: u8 = 0x40;
+pub(super) const BPF_MEM: u8 = 0x60;
+pub(super) const BPF_MEMSX: u8 = 0x80;
+pub(super) const BPF_ATOMIC: u8 = 0xc0;
+
+// Operand sources, where the immediate is the default source
+pub(super) const BPF_X: u8 = 0x08;
+
+// Arithmetic operations
+pub(super) const BPF_ADD: u8 = 0x00;
+pub(super) const BPF_SUB: u8 = 0x10;
+pub(super) const BPF_MUL: u8 = 0x20;
+pub(super) const BPF_DIV: u8 = 0x30;
+pub(super) const BPF_OR: u8 = 0x40;
+pub(super) const BPF_AND: u8 = 0x50;
+pub(super) const BPF_LSH: u8 = 0x60;
+pub(super) const BPF_RSH: u8 = 0x70;
+pub(super) const BPF_NEG: u8 = 0x80;
+pub(super) const BPF_MOD: u8 = 0x90;
+pub(super) const BPF_XOR: u8 = 0xa0;
+pub(super) const BPF_MOV: u8 = 0xb0;
+pub(super) const BPF_ARSH: u8 = 0xc0;
+pub(super) const BPF_END: u8 = 0xd0;
+
+// Byte swap directions, which share the bit with the operand sources
+pub(super) const BPF_TO_BE: u8 = 0x08;
+
+// Jump operations
+pub(super) const BPF_JA: u8 = 0x00;
+pub(super) const BPF_JEQ: u8 = 0x10;
+pub(super) const BPF_JGT: u8 = 0x20;
+pub(super) const BPF_JGE: u8 = 0x30;
+pub(super) const BPF_JSET: u8 = 0x40;
+pub(super) const BPF_JNE: u8 = 0x50;
+pub(super) const BPF_JSGT: u8 = 0x60;
+pub(super) const BPF_JSGE: u8 = 0x70;
+pub(super) const BPF_CALL: u8 = 0x80;
+pub(super) const BPF_EXIT: u8 = 0x90;
+pub(super) const BPF_JLT: u8 = 0xa0;
+pub(super) const BPF_JLE: u8 = 0xb0;
+pub(super) const BPF_JSLT: u8 = 0xc0;
+pub(super) const BPF_JSLE: u8 = 0xd0;
+
+// Atomic operations, which are stored in the immediate
+pub(super) const BPF_FETCH: i32 = 0x01;
+pub(super) const BPF_XCHG: i32 = 0xe0 | BPF_FETCH;
+pub(super) const BPF_CMPXCHG: i32 = 0xf0 | BPF_FETCH;
+
+/// The source register of a 64-bit immediate load that refers to a map by its file descriptor.
+pub(super) const BPF_PSEUDO_MAP_FD: usize = 1;
+
+/// Returns the size in bytes of a load or store.
+pub(super) fn size_of_access(code: u8) -> usize {
+    match code & 0x18 {
+        BPF_W => 4,
+        BPF_H => 2,
+        BPF_B => 1,
+        _ => 8,
+    }
+}
@@ -0,0 +1,679 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The eBPF interpreter.
+//!
+//! Programs see a virtual address space, where the upper 32 bits of an address select a memory
+//! region and the lower 32 bits are the offset in the region. The regions are the stack, the
+//! context, the maps (which can be passed to helpers but cannot be accessed), and the regions
+//! returned by helpers, i.e., map values and ring buffer records. Every memory access is checked
+//! against the bounds of its region, so programs cannot access the memory outside the regions.
+
+use ostd::cpu::CpuId;
+
+use super::{
+    insn::*,
+    map::{BpfMap, BpfUpdateMode},
+    prog::{BpfProg, BpfProgType},
+};
+use crate::{prelude::*, time::clocks::MonotonicClock, util::random::getrandom};
+
+/// The helper functions that programs can call.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/bpf.h#L5436>.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+pub(super) enum BpfHelper {
+    MapLookupElem = 1,
+    MapUpdateElem = 2,
+    MapDeleteElem = 3,
+    KtimeGetNs = 5,
+    GetPrandomU32 = 7,
+    GetSmpProcessorId = 8,
+    SkbLoadBytes = 26,
+    RingbufOutput = 130,
+    RingbufReserve = 131,
+    RingbufSubmit = 132,
+    RingbufDiscard = 133,
+}
+
+impl BpfHelper {
+    /// Returns whether programs of the type can call the helper.
+    pub(super) fn is_allowed_for(self, prog_type: BpfProgType) -> bool {
+        match self {
+            Self::SkbLoadBytes => prog_type == BpfProgType::SocketFilter,
+            _ => true,
+        }
+    }
+}
+
+/// The maximum number of instructions executed in a run.
+///
+/// The verifier does not reject loops, so a run is aborted if it executes too many instructions.
+const MAX_EXECUTED_INSNS: usize = 1 << 20;
+
+/// The maximum number of regions returned by helpers in a run.
+const MAX_DYN_REGIONS: usize = 256;
+
+const STACK_REGION: u64 = 1;
+const CONTEXT_REGION: u64 = 2;
+/// The region of the first map.
+const MAP_REGION_BASE: u64 = 3;
+
+/// A region returned by a helper.
+enum DynRegion {
+    /// The value of a map element.
+    MapValue { map: usize, key: Box<[u8]> },
+    /// A reserved record in a ring buffer.
+    RingBufRecord {
+        map: usize,
+        pos: u64,
+        len: usize,
+        is_committed: bool,
+    },
+}
+
+/// An interpreter that runs a program once.
+pub(super) struct Interpreter<'a> {
+    prog: &'a BpfProg,
+    regs: [u64; MAX_BPF_REG],
+    stack: [u8; MAX_BPF_STACK],
+    ctx: &'a mut [u8],
+    packet: &'a [u8],
+    dyn_regions: Vec<DynRegion>,
+}
+
+impl<'a> Interpreter<'a> {
+    /// Creates an interpreter that runs the program with the context.
+    ///
+    /// The context is read-only to the program. The packet can only be read by the legacy packet
+    /// loads and the `bpf_skb_load_bytes` helper.
+    pub(super) fn new(prog: &'a BpfProg, ctx: &'a mut [u8], packet: &'a [u8]) -> Self {
+        Self {
+            prog,
+            regs: [0; MAX_BPF_REG],
+            stack: [0; MAX_BPF_STACK],
+            ctx,
+            packet,
+            dyn_regions: Vec::new(),
+        }
+    }
+
+    /// Runs the program and returns its return value.
+    ///
+    /// This method returns `None` if the program is aborted, e.g., because it accesses invalid
+    /// memory or runs for too long.
+    pub(super) fn run(mut self) -> Option<u64> {
+        self.regs[1] = addr_of(CONTEXT_REGION, 0);
+        self.regs[BPF_REG_FP] = addr_of(STACK_REGION, MAX_BPF_STACK);
+
+        let result = self.execute();
+
+        // Records that are reserved but not committed would block user space forever.
+        for region in self.dyn_regions.iter() {
+            if let DynRegion::RingBufRecord {
+                map,
+                pos,
+                is_committed: false,
+                ..
+            } = region
+            {
+                let ringbuf = self.prog.maps()[*map].as_ringbuf().unwrap();
+                ringbuf.commit(*pos, true, 0);
+            }
+        }
+
+        result
+    }
+
+    fn execute(&mut self) -> Option<u64> {
+        let insns = self.prog.insns();
+        let mut pc = 0;
+
+        for _ in 0..MAX_EXECUTED_INSNS {
+            // The verifier ensures that the program counter is always in bounds.
+            let insn = insns[pc];
+            pc += 1;
+
+            match insn.class() {
+                BPF_ALU | BPF_ALU64 => self.exec_alu(&insn),
+                BPF_JMP | BPF_JMP32 => match insn.code & 0xf0 {
+                    BPF_EXIT => return Some(self.regs[0]),
+                    BPF_CALL => self.call_helper(BpfHelper::try_from(insn.imm).unwrap())?,
+                    _ => {
+                        if self.is_jump_taken(&insn) {
+                            pc = pc.wrapping_add_signed(insn.off as isize);
+                        }
+                    }
+                },
+                BPF_LDX => {
+                    let addr = self.regs[insn.src()].wrapping_add(insn.off as u64);
+                    let size = size_of_access(insn.code);
+                    let val = self.load(addr, size)?;
+                    self.regs[insn.dst()] = if insn.code & 0xe0 == BPF_MEMSX {
+                        sign_extend(val, size)
+                    } else {
+                        val
+                    };
+                }
+                BPF_ST => {
+                    let addr = self.regs[insn.dst()].wrapping_add(insn.off as u64);
+                    self.store(addr, size_of_access(insn.code), insn.imm as u64)?;
+                }
+                BPF_STX if insn.code & 0xe0 == BPF_MEM => {
+                    let addr = self.regs[insn.dst()].wrapping_add(insn.off as u64);
+                    let val = self.regs[insn.src()];
+                    self.store(addr, size_of_access(insn.code), val)?;
+                }
+                BPF_STX => self.exec_atomic(&insn)?,
+                BPF_LD if insn.is_ld_imm64() => {
+                    let next = insns[pc];
+                    pc += 1;
+                    self.regs[insn.dst()] = if insn.src() == BPF_PSEUDO_MAP_FD {
+                        // The file descriptor has been replaced by the index of the map.
+                        addr_of(MAP_REGION_BASE + insn.imm as u64, 0)
+                    } else {
+                        insn.imm as u32 as u64 | (next.imm as u32 as u64) << 32
+                    };
+                }
+                BPF_LD => {
+                    // Like Linux, the program exits with zero if the packet load is out of bounds.
+                    let Some(val) = self.load_packet(&insn) else {
+                        return Some(0);
+                    };
+                    self.regs[0] = val;
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        None
+    }
+
+    fn exec_alu(&mut self, insn: &BpfInsn) {
+        let is_64 = insn.class() == BPF_ALU64;
+        let op = insn.code & 0xf0;
+        let dst = self.regs[insn.dst()];
+
+        if op == BPF_END {
+            // The kernel is little-endian, so only conversions to big-endian and unconditional
+            // byte swaps need to swap the bytes.
+            let should_swap = is_64 || insn.code & BPF_TO_BE != 0;
+            self.regs[insn.dst()] = byte_swap(dst, insn.imm, should_swap);
+            return;
+        }
+
+        let src = if insn.code & BPF_X != 0 {
+            self.regs[insn.src()]
+        } else {
+            insn.imm as u64
+        };
+        self.regs[insn.dst()] = if is_64 {
+            alu64(op, insn.off, dst, src)
+        } else {
+            alu32(op, insn.off, dst as u32, src as u32) as u64
+        };
+    }
+
+    fn is_jump_taken(&self, insn: &BpfInsn) -> bool {
+        let op = insn.code & 0xf0;
+        if op == BPF_JA {
+            return true;
+        }
+
+        let dst = self.regs[insn.dst()];
+        let src = if insn.code & BPF_X != 0 {
+            self.regs[insn.src()]
+        } else {
+            insn.imm as u64
+        };
+        let (dst, src, signed_dst, signed_src) = if insn.class() == BPF_JMP32 {
+            let (dst, src) = (dst as u32, src as u32);
+            (dst as u64, src as u64, dst as i32 as i64, src as i32 as i64)
+        } else {
+            (dst, src, dst as i64, src as i64)
+        };
+
+        match op {
+            BPF_JEQ => dst == src,
+            BPF_JNE => dst != src,
+            BPF_JGT => dst > src,
+            BPF_JGE => dst >= src,
+            BPF_JLT => dst < src,
+            BPF_JLE => dst <= src,
+            BPF_JSET => dst & src != 0,
+            BPF_JSGT => signed_dst > signed_src,
+            BPF_JSGE => signed_dst >= signed_src,
+            BPF_JSLT => signed_dst < signed_src,
+            BPF_JSLE => signed_dst <= signed_src,
+            _ => unreachable!(),
+        }
+    }
+
+    fn exec_atomic(&mut self, insn: &BpfInsn) -> Option<()> {
+        let addr = self.regs[insn.dst()].wrapping_add(insn.off as u64);
+        let size = size_of_access(insn.code);
+        let src = self.regs[insn.src()];
+        let expected = truncate(self.regs[0], size);
+
+        let old = self.with_mem(addr, size, true, |bytes| {
+            let old = from_le_bytes(bytes);
+            let new = match insn.imm {
+                BPF_XCHG => src,
+                BPF_CMPXCHG if old == expected => src,
+                BPF_CMPXCHG => old,
+                op => match (op & !BPF_FETCH) as u8 {
+                    BPF_ADD => old.wrapping_add(src),
+                    BPF_OR => old | src,
+                    BPF_AND => old & src,
+                    BPF_XOR => old ^ src,
+                    _ => unreachable!(),
+                },
+            };
+            bytes.copy_from_slice(&new.to_le_bytes()[..size]);
+            old
+        })?;
+
+        match insn.imm {
+            BPF_CMPXCHG => self.regs[0] = old,
+            op if op & BPF_FETCH != 0 => self.regs[insn.src()] = old,
+            _ => (),
+        }
+        Some(())
+    }
+
+    /// Loads big-endian data from the packet.
+    fn load_packet(&self, insn: &BpfInsn) -> Option<u64> {
+        let offset = if insn.code & 0xe0 == BPF_IND {
+            (self.regs[insn.src()] as i32).wrapping_add(insn.imm)
+        } else {
+            insn.imm
+        };
+        let offset = usize::try_from(offset).ok()?;
+        let size = size_of_access(insn.code);
+        let bytes = self.packet.get(offset..offset.checked_add(size)?)?;
+
+        Some(bytes.iter().fold(0, |val, byte| (val << 8) | *byte as u64))
+    }
+
+    fn call_helper(&mut self, helper: BpfHelper) -> Option<()> {
+        let [_, arg1, arg2, arg3, arg4, ..] = self.regs;
+
+        self.regs[0] = match helper {
+            BpfHelper::MapLookupElem => self.map_lookup_elem(arg1, arg2).unwrap_or(0),
+            BpfHelper::MapUpdateElem => {
+                to_return_value(self.map_update_elem(arg1, arg2, arg3, arg4))
+            }
+            BpfHelper::MapDeleteElem => to_return_value(self.map_delete_elem(arg1, arg2)),
+            BpfHelper::KtimeGetNs => MonotonicClock::get().read_time().as_nanos() as u64,
+            BpfHelper::GetPrandomU32 => {
+                let mut bytes = [0; size_of::<u32>()];
+                getrandom(&mut bytes);
+                u32::from_ne_bytes(bytes) as u64
+            }
+            BpfHelper::GetSmpProcessorId => u32::from(CpuId::current_racy()) as u64,
+            BpfHelper::SkbLoadBytes => {
+                to_return_value(self.skb_load_bytes(arg1, arg2, arg3, arg4))
+            }
+            BpfHelper::RingbufOutput => {
+                to_return_value(self.ringbuf_output(arg1, arg2, arg3, arg4))
+            }
+            BpfHelper::RingbufReserve => self.ringbuf_reserve(arg1, arg2, arg3).unwrap_or(0),
+            BpfHelper::RingbufSubmit => {
+                self.ringbuf_commit(arg1, false, arg2)?;
+                0
+            }
+            BpfHelper::RingbufDiscard => {
+                self.ringbuf_commit(arg1, true, arg2)?;
+                0
+            }
+        };
+
+        Some(())
+    }
+
+    fn map_lookup_elem(&mut self, map_addr: u64, key_addr: u64) -> Result<u64> {
+        let (map_index, map) = self.map_of(map_addr)?;
+        let key = self.read_bytes(key_addr, map.attr().key_size as usize)?;
+        map.with_value(&key, &mut |_| ())?;
+
+        // Looking up the same element twice returns the same pointer.
+        let existing = self.dyn_regions.iter().position(|region| {
+            matches!(region, DynRegion::MapValue { map, key: region_key }
+                if *map == map_index && **region_key == *key)
+        });
+        let index = match existing {
+            Some(index) => index,
+            None => self.push_dyn_region(DynRegion::MapValue {
+                map: map_index,
+                key: key.into(),
+            })?,
+        };
+
+        Ok(self.addr_of_dyn_region(index))
+    }
+
+    fn map_update_elem(
+        &mut self,
+        map_addr: u64,
+        key_addr: u64,
+        value_addr: u64,
+        flags: u64,
+    ) -> Result<u64> {
+        let (_, map) = self.map_of(map_addr)?;
+        let key = self.read_bytes(key_addr, map.attr().key_size as usize)?;
+        let value = self.read_bytes(value_addr, map.attr().value_size as usize)?;
+        map.update(&key, &value, BpfUpdateMode::from_flags(flags)?)?;
+        Ok(0)
+    }
+
+    fn map_delete_elem(&mut self, map_addr: u64, key_addr: u64) -> Result<u64> {
+        let (_, map) = self.map_of(map_addr)?;
+        let key = self.read_bytes(key_addr, map.attr().key_size as usize)?;
+        map.delete(&key)?;
+        Ok(0)
+    }
+
+    fn skb_load_bytes(
+        &mut self,
+        ctx_addr: u64,
+        offset: u64,
+        to_addr: u64,
+        len: u64,
+    ) -> Result<u64> {
+        if ctx_addr != addr_of(CONTEXT_REGION, 0) || len == 0 {
+            return_errno_with_message!(Errno::EINVAL, "the arguments are invalid");
+        }
+
+        let packet = self.packet;
+        let data = usize::try_from(offset as u32).ok().and_then(|offset| {
+            let end = offset.checked_add(usize::try_from(len).ok()?)?;
+            packet.get(offset..end)
+        });
+        let is_in_bounds = self.with_mem(to_addr, len as usize, true, |buf| {
+            // Like Linux, the buffer is zeroed if the data is out of bounds.
+            match data {
+                Some(data) => buf.copy_from_slice(data),
+                None => buf.fill(0),
+            }
+            data.is_some()
+        });
+
+        match is_in_bounds {
+            Some(true) => Ok(0),
+            Some(false) => return_errno_with_message!(Errno::EFAULT, "the data is out of bounds"),
+            None => return_errno_with_message!(Errno::EFAULT, "the buffer is invalid"),
+        }
+    }
+
+    fn ringbuf_output(
+        &mut self,
+        map_addr: u64,
+        data_addr: u64,
+        size: u64,
+        flags: u64,
+    ) -> Result<u64> {
+        let (_, map) = self.map_of(map_addr)?;
+        let Some(ringbuf) = map.as_ringbuf() else {
+            return_errno_with_message!(Errno::EINVAL, "the map is not a ring buffer");
+        };
+        let data = self.read_bytes(data_addr, usize::try_from(size)?)?;
+        ringbuf.output(&data, flags)?;
+        Ok(0)
+    }
+
+    fn ringbuf_reserve(&mut self, map_addr: u64, size: u64, flags: u64) -> Result<u64> {
+        let (map_index, map) = self.map_of(map_addr)?;
+        let Some(ringbuf) = map.as_ringbuf() else {
+            return_errno_with_message!(Errno::EINVAL, "the map is not a ring buffer");
+        };
+        if flags != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the flags are invalid");
+        }
+        // Check the limit before reserving the space, which cannot be given back.
+        if self.dyn_regions.len() >= MAX_DYN_REGIONS {
+            return_errno_with_message!(Errno::ENOMEM, "too many regions are returned");
+        }
+
+        let len = usize::try_from(size)?;
+        let Some(pos) = ringbuf.reserve(len) else {
+            return_errno_with_message!(Errno::ENOSPC, "the ring buffer is full");
+        };
+        let index = self.push_dyn_region(DynRegion::RingBufRecord {
+            map: map_index,
+            pos,
+            len,
+            is_committed: false,
+        })?;
+
+        Ok(self.addr_of_dyn_region(index))
+    }
+
+    /// Commits a reserved record.
+    ///
+    /// This method returns `None` if the address is not a record that is not committed.
+    fn ringbuf_commit(&mut self, addr: u64, is_discarded: bool, flags: u64) -> Option<()> {
+        if addr & u32::MAX as u64 != 0 {
+            return None;
+        }
+        let index = (addr >> 32).checked_sub(self.dyn_region_base())?;
+
+        let prog = self.prog;
+        let Some(DynRegion::RingBufRecord {
+            map,
+            pos,
+            is_committed,
+            ..
+        }) = self.dyn_regions.get_mut(index as usize)
+        else {
+            return None;
+        };
+        if *is_committed {
+            return None;
+        }
+
+        prog.maps()[*map]
+            .as_ringbuf()
+            .unwrap()
+            .commit(*pos, is_discarded, flags);
+        *is_committed = true;
+        Some(())
+    }
+
+    /// Returns the map at the address, which is a pointer to the map.
+    fn map_of(&self, addr: u64) -> Result<(usize, &'a Arc<dyn BpfMap>)> {
+        let maps = self.prog.maps();
+        let map = (addr & u32::MAX as u64 == 0)
+            .then(|| (addr >> 32).checked_sub(MAP_REGION_BASE))
+            .flatten()
+            .and_then(|index| Some((index as usize, maps.get(index as usize)?)));
+        map.ok_or_else(|| Error::with_message(Errno::EINVAL, "the map pointer is invalid"))
+    }
+
+    fn dyn_region_base(&self) -> u64 {
+        MAP_REGION_BASE + self.prog.maps().len() as u64
+    }
+
+    fn addr_of_dyn_region(&self, index: usize) -> u64 {
+        addr_of(self.dyn_region_base() + index as u64, 0)
+    }
+
+    fn push_dyn_region(&mut self, region: DynRegion) -> Result<usize> {
+        if self.dyn_regions.len() >= MAX_DYN_REGIONS {
+            return_errno_with_message!(Errno::ENOMEM, "too many regions are returned");
+        }
+        self.dyn_regions.push(region);
+        Ok(self.dyn_regions.len() - 1)
+    }
+
+    fn load(&mut self, addr: u64, size: usize) -> Option<u64> {
+        self.with_mem(addr, size, false, |bytes| from_le_bytes(bytes))
+    }
+
+    fn store(&mut self, addr: u64, size: usize, val: u64) -> Option<()> {
+        self.with_mem(addr, size, true, |bytes| {
+            bytes.copy_from_slice(&val.to_le_bytes()[..size]);
+        })
+    }
+
+    fn read_bytes(&mut self, addr: u64, len: usize) -> Result<Vec<u8>> {
+        self.with_mem(addr, len, false, |bytes| bytes.to_vec())
+            .ok_or_else(|| Error::with_message(Errno::EFAULT, "the memory is invalid"))
+    }
+
+    /// Calls `f` with the memory of `len` bytes at the address.
+    ///
+    /// This method returns `None` if the memory is out of bounds or cannot be accessed.
+    fn with_mem<R>(
+        &mut self,
+        addr: u64,
+        len: usize,
+        is_write: bool,
+        f: impl FnOnce(&mut [u8]) -> R,
+    ) -> Option<R> {
+        let region = addr >> 32;
+        let offset = (addr & u32::MAX as u64) as usize;
+        let range = offset..offset.checked_add(len)?;
+
+        match region {
+            STACK_REGION => return Some(f(self.stack.get_mut(range)?)),
+            CONTEXT_REGION if !is_write => return Some(f(self.ctx.get_mut(range)?)),
+            _ => (),
+        }
+
+        let index = region.checked_sub(self.dyn_region_base())?;
+        let maps = self.prog.maps();
+        match self.dyn_regions.get(index as usize)? {
+            DynRegion::MapValue { map, key } => {
+                let mut f = Some(f);
+                let mut result = None;
+                maps[*map]
+                    .with_value(key, &mut |value| {
+                        if let Some(bytes) = value.get_mut(range.clone()) {
+                            result = Some((f.take().unwrap())(bytes));
+                        }
+                    })
+                    .ok()?;
+                result
+            }
+            DynRegion::RingBufRecord {
+                map,
+                pos,
+                len: record_len,
+                is_committed: false,
+            } => {
+                if range.end > *record_len {
+                    return None;
+                }
+                let ringbuf = maps[*map].as_ringbuf().unwrap();
+                let mut buf = vec![0; len];
+                ringbuf.read_data(pos + offset as u64, &mut buf);
+                let result = f(&mut buf);
+                if is_write {
+                    ringbuf.write_data(pos + offset as u64, &buf);
+                }
+                Some(result)
+            }
+            DynRegion::RingBufRecord { .. } => None,
+        }
+    }
+}
+
+fn addr_of(region: u64, offset: usize) -> u64 {
+    (region << 32) | offset as u64
+}
+
+fn alu64(op: u8, off: i16, dst: u64, src: u64) -> u64 {
+    // Like Linux, division by zero yields zero and modulo by zero leaves the dividend unchanged.
+    // The offset is one for signed division and modulo.
+    match op {
+        BPF_ADD => dst.wrapping_add(src),
+        BPF_SUB => dst.wrapping_sub(src),
+        BPF_MUL => dst.wrapping_mul(src),
+        BPF_DIV if src == 0 => 0,
+        BPF_DIV if off == 1 => (dst as i64).wrapping_div(src as i64) as u64,
+        BPF_DIV => dst / src,
+        BPF_MOD if src == 0 => dst,
+        BPF_MOD if off == 1 => (dst as i64).wrapping_rem(src as i64) as u64,
+        BPF_MOD => dst % src,
+        BPF_OR => dst | src,
+        BPF_AND => dst & src,
+        BPF_XOR => dst ^ src,
+        BPF_LSH => dst << (src & 63),
+        BPF_RSH => dst >> (src & 63),
+        BPF_ARSH => ((dst as i64) >> (src & 63)) as u64,
+        BPF_NEG => dst.wrapping_neg(),
+        // The offset is the number of bits to sign-extend.
+        BPF_MOV if off == 0 => src,
+        BPF_MOV => sign_extend(src, off as usize / 8),
+        _ => unreachable!(),
+    }
+}
+
+fn alu32(op: u8, off: i16, dst: u32, src: u32) -> u32 {
+    match op {
+        BPF_ADD => dst.wrapping_add(src),
+        BPF_SUB => dst.wrapping_sub(src),
+        BPF_MUL => dst.wrapping_mul(src),
+        BPF_DIV if src == 0 => 0,
+        BPF_DIV if off == 1 => (dst as i32).wrapping_div(src as i32) as u32,
+        BPF_DIV => dst / src,
+        BPF_MOD if src == 0 => dst,
+        BPF_MOD if off == 1 => (dst as i32).wrapping_rem(src as i32) as u32,
+        BPF_MOD => dst % src,
+        BPF_OR => dst | src,
+        BPF_AND => dst & src,
+        BPF_XOR => dst ^ src,
+        BPF_LSH => dst << (src & 31),
+        BPF_RSH => dst >> (src & 31),
+        BPF_ARSH => ((dst as i32) >> (src & 31)) as u32,
+        BPF_NEG => dst.wrapping_neg(),
+        BPF_MOV if off == 0 => src,
+        BPF_MOV => sign_extend(src as u64, off as usize / 8) as u32,
+        _ => unreachable!(),
+    }
+}
+
+fn byte_swap(val: u64, width: i32, should_swap: bool) -> u64 {
+    match (width, should_swap) {
+        (16, true) => (val as u16).swap_bytes() as u64,
+        (16, false) => val as u16 as u64,
+        (32, true) => (val as u32).swap_bytes() as u64,
+        (32, false) => val as u32 as u64,
+        (_, true) => val.swap_bytes(),
+        (_, false) => val,
+    }
+}
+
+/// Sign-extends the lowest `size` bytes of the value.
+fn sign_extend(val: u64, size: usize) -> u64 {
+    match size {
+        1 => val as i8 as u64,
+        2 => val as i16 as u64,
+        4 => val as i32 as u64,
+        _ => val,
+    }
+}
+
+/// Keeps the lowest `size` bytes of the value.
+fn truncate(val: u64, size: usize) -> u64 {
+    if size >= size_of::<u64>() {
+        val
+    } else {
+        val & ((1 << (size * 8)) - 1)
+    }
+}
+
+fn from_le_bytes(bytes: &[u8]) -> u64 {
+    let mut buf = [0; size_of::<u64>()];
+    buf[..bytes.len()].copy_from_slice(bytes);
+    u64::from_le_bytes(buf)
+}
+
+/// Converts the result of a helper to its return value, which is a negative error number on
+/// failure.
+fn to_return_value(result: Result<u64>) -> u64 {
+    match result {
+        Ok(val) => val,
+        Err(err) => -(err.error() as i64) as u64,
+    }
+}
@@ -0,0 +1,91 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::{BpfMap, BpfMapAttr, BpfUpdateMode, MAX_ELEM_SIZE, alloc_zeroed};
+use crate::prelude::*;
+
+/// An array map (`BPF_MAP_TYPE_ARRAY`).
+///
+/// The keys are 32-bit indexes, and all the elements exist and are zeroed initially.
+#[derive(Debug)]
+pub(super) struct ArrayMap {
+    attr: BpfMapAttr,
+    values: SpinLock<Vec<u8>>,
+}
+
+impl ArrayMap {
+    pub(super) fn new(attr: BpfMapAttr) -> Result<Self> {
+        if attr.key_size != size_of::<u32>() as u32 || attr.value_size == 0 {
+            return_errno_with_message!(Errno::EINVAL, "the key or value size is invalid");
+        }
+        if attr.max_entries == 0 || attr.map_flags != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the map attributes are invalid");
+        }
+        if attr.value_size > MAX_ELEM_SIZE {
+            return_errno_with_message!(Errno::E2BIG, "the value size is too large");
+        }
+
+        let len = (attr.value_size as usize)
+            .checked_mul(attr.max_entries as usize)
+            .ok_or_else(|| Error::with_message(Errno::ENOMEM, "the map is too large"))?;
+        Ok(Self {
+            attr,
+            values: SpinLock::new(alloc_zeroed(len)?),
+        })
+    }
+
+    /// Returns the index of the key, if it is in bounds.
+    fn index_of(&self, key: &[u8]) -> Option<usize> {
+        let index = u32::from_ne_bytes(key.try_into().unwrap());
+        (index < self.attr.max_entries).then_some(index as usize)
+    }
+
+    fn range_of(&self, index: usize) -> core::ops::Range<usize> {
+        let value_size = self.attr.value_size as usize;
+        index * value_size..(index + 1) * value_size
+    }
+}
+
+impl BpfMap for ArrayMap {
+    fn attr(&self) -> &BpfMapAttr {
+        &self.attr
+    }
+
+    fn with_value(&self, key: &[u8], f: &mut dyn FnMut(&mut [u8])) -> Result<()> {
+        let Some(index) = self.index_of(key) else {
+            return_errno_with_message!(Errno::ENOENT, "the index is out of bounds");
+        };
+
+        f(&mut self.values.lock()[self.range_of(index)]);
+        Ok(())
+    }
+
+    fn update(&self, key: &[u8], value: &[u8], mode: BpfUpdateMode) -> Result<()> {
+        let Some(index) = self.index_of(key) else {
+            return_errno_with_message!(Errno::E2BIG, "the index is out of bounds");
+        };
+        // All the elements exist.
+        if mode == BpfUpdateMode::NoExist {
+            return_errno_with_message!(Errno::EEXIST, "the element already exists");
+        }
+
+        self.values.lock()[self.range_of(index)].copy_from_slice(value);
+        Ok(())
+    }
+
+    fn delete(&self, _key: &[u8]) -> Result<()> {
+        return_errno_with_message!(Errno::EINVAL, "the elements of array maps cannot be deleted");
+    }
+
+    fn get_next_key(&self, key: Option<&[u8]>, next_key: &mut [u8]) -> Result<()> {
+        let next_index = match key.and_then(|key| self.index_of(key)) {
+            Some(index) if index + 1 == self.attr.max_entries as usize => {
+                return_errno_with_message!(Errno::ENOENT, "the key is the last key");
+            }
+            Some(index) => index + 1,
+            None => 0,
+        };
+
+        next_key.copy_from_slice(&(next_index as u32).to_ne_bytes());
+        Ok(())
+    }
+}
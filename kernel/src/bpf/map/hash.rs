@@ -0,0 +1,96 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::ops::Bound;
+
+use super::{BPF_F_NO_PREALLOC, BpfMap, BpfMapAttr, BpfUpdateMode, MAX_ELEM_SIZE};
+use crate::prelude::*;
+
+/// A hash map (`BPF_MAP_TYPE_HASH`).
+///
+/// The elements are kept in a B-tree rather than a hash table, so that iterating over the keys
+/// with `BPF_MAP_GET_NEXT_KEY` is stable while the map is being modified.
+#[derive(Debug)]
+pub(super) struct HashMap {
+    attr: BpfMapAttr,
+    elems: SpinLock<BTreeMap<Box<[u8]>, Box<[u8]>>>,
+}
+
+impl HashMap {
+    pub(super) fn new(attr: BpfMapAttr) -> Result<Self> {
+        if attr.key_size == 0 || attr.value_size == 0 {
+            return_errno_with_message!(Errno::EINVAL, "the key or value size is invalid");
+        }
+        if attr.max_entries == 0 || attr.map_flags & !BPF_F_NO_PREALLOC != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the map attributes are invalid");
+        }
+        if attr.key_size > MAX_ELEM_SIZE || attr.value_size > MAX_ELEM_SIZE {
+            return_errno_with_message!(Errno::E2BIG, "the key or value size is too large");
+        }
+
+        Ok(Self {
+            attr,
+            elems: SpinLock::new(BTreeMap::new()),
+        })
+    }
+}
+
+impl BpfMap for HashMap {
+    fn attr(&self) -> &BpfMapAttr {
+        &self.attr
+    }
+
+    fn with_value(&self, key: &[u8], f: &mut dyn FnMut(&mut [u8])) -> Result<()> {
+        let mut elems = self.elems.lock();
+        let Some(value) = elems.get_mut(key) else {
+            return_errno_with_message!(Errno::ENOENT, "the key does not exist");
+        };
+
+        f(value);
+        Ok(())
+    }
+
+    fn update(&self, key: &[u8], value: &[u8], mode: BpfUpdateMode) -> Result<()> {
+        let mut elems = self.elems.lock();
+
+        if let Some(old_value) = elems.get_mut(key) {
+            if mode == BpfUpdateMode::NoExist {
+                return_errno_with_message!(Errno::EEXIST, "the key already exists");
+            }
+            old_value.copy_from_slice(value);
+            return Ok(());
+        }
+
+        if mode == BpfUpdateMode::Exist {
+            return_errno_with_message!(Errno::ENOENT, "the key does not exist");
+        }
+        if elems.len() >= self.attr.max_entries as usize {
+            return_errno_with_message!(Errno::E2BIG, "the map is full");
+        }
+        elems.insert(key.into(), value.into());
+        Ok(())
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<()> {
+        if self.elems.lock().remove(key).is_none() {
+            return_errno_with_message!(Errno::ENOENT, "the key does not exist");
+        }
+        Ok(())
+    }
+
+    fn get_next_key(&self, key: Option<&[u8]>, next_key: &mut [u8]) -> Result<()> {
+        let elems = self.elems.lock();
+
+        let next = match key {
+            Some(key) if elems.contains_key(key) => elems
+                .range::<[u8], _>((Bound::Excluded(key), Bound::Unbounded))
+                .next(),
+            _ => elems.iter().next(),
+        };
+        let Some((next, _)) = next else {
+            return_errno_with_message!(Errno::ENOENT, "there are no more keys");
+        };
+
+        next_key.copy_from_slice(next);
+        Ok(())
+    }
+}
@@ -0,0 +1,117 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! eBPF maps, which store data shared by programs and user space.
+
+use array::ArrayMap;
+use hash::HashMap;
+pub use ringbuf::RingBufMap;
+
+use crate::prelude::*;
+
+mod array;
+mod hash;
+mod ringbuf;
+
+/// The type of an eBPF map.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/bpf.h#L880>.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+pub enum BpfMapType {
+    Hash = 1,
+    Array = 2,
+    RingBuf = 27,
+}
+
+/// The attributes of an eBPF map, which are fixed when the map is created.
+#[derive(Debug, Clone, Copy)]
+pub struct BpfMapAttr {
+    pub map_type: BpfMapType,
+    pub key_size: u32,
+    pub value_size: u32,
+    pub max_entries: u32,
+    pub map_flags: u32,
+}
+
+/// How to update an element of an eBPF map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BpfUpdateMode {
+    /// Creates a new element or updates an existing element (`BPF_ANY`).
+    Any,
+    /// Creates a new element only if it does not exist (`BPF_NOEXIST`).
+    NoExist,
+    /// Updates an existing element only (`BPF_EXIST`).
+    Exist,
+}
+
+impl BpfUpdateMode {
+    /// Parses the flags of `BPF_MAP_UPDATE_ELEM` and the `bpf_map_update_elem` helper.
+    pub fn from_flags(flags: u64) -> Result<Self> {
+        match flags {
+            0 => Ok(Self::Any),
+            1 => Ok(Self::NoExist),
+            2 => Ok(Self::Exist),
+            _ => return_errno_with_message!(Errno::EINVAL, "the update flags are invalid"),
+        }
+    }
+}
+
+/// An eBPF map.
+///
+/// The keys and the values passed to the methods must have the sizes in the attributes of the
+/// map.
+pub trait BpfMap: Debug + Send + Sync {
+    /// Returns the attributes of the map.
+    fn attr(&self) -> &BpfMapAttr;
+
+    /// Calls `f` with the value of the key, which can be modified in place.
+    fn with_value(&self, key: &[u8], f: &mut dyn FnMut(&mut [u8])) -> Result<()>;
+
+    /// Updates the value of the key.
+    fn update(&self, key: &[u8], value: &[u8], mode: BpfUpdateMode) -> Result<()>;
+
+    /// Deletes the element of the key.
+    fn delete(&self, key: &[u8]) -> Result<()>;
+
+    /// Writes the key that follows `key` to `next_key`.
+    ///
+    /// If `key` is `None` or does not exist, the first key is written.
+    fn get_next_key(&self, key: Option<&[u8]>, next_key: &mut [u8]) -> Result<()>;
+
+    /// Copies the value of the key to `value`.
+    fn lookup(&self, key: &[u8], value: &mut [u8]) -> Result<()> {
+        self.with_value(key, &mut |map_value| value.copy_from_slice(map_value))
+    }
+
+    /// Returns the map as a ring buffer, if it is.
+    fn as_ringbuf(&self) -> Option<&RingBufMap> {
+        None
+    }
+}
+
+/// Creates an eBPF map with the attributes.
+pub fn new_map(attr: BpfMapAttr) -> Result<Arc<dyn BpfMap>> {
+    let map: Arc<dyn BpfMap> = match attr.map_type {
+        BpfMapType::Hash => Arc::new(HashMap::new(attr)?),
+        BpfMapType::Array => Arc::new(ArrayMap::new(attr)?),
+        BpfMapType::RingBuf => Arc::new(RingBufMap::new(attr)?),
+    };
+    Ok(map)
+}
+
+/// The flag to allocate hash map elements on demand.
+///
+/// The flag has no effect, since the elements are always allocated on demand.
+const BPF_F_NO_PREALLOC: u32 = 1 << 0;
+
+/// The maximum size of the keys and the values in bytes.
+const MAX_ELEM_SIZE: u32 = 1 << 16;
+
+/// Allocates a zeroed buffer, failing with `ENOMEM` instead of panicking if memory runs out.
+fn alloc_zeroed(len: usize) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    buf.try_reserve_exact(len)
+        .map_err(|_| Error::with_message(Errno::ENOMEM, "the map is too large"))?;
+    buf.resize(len, 0);
+    Ok(buf)
+}
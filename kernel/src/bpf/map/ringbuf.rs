@@ -0,0 +1,248 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{Ordering, fence};
+
+use align_ext::AlignExt;
+use ostd::mm::VmIo;
+
+use super::{BpfMap, BpfMapAttr, BpfUpdateMode};
+use crate::{
+    events::IoEvents,
+    prelude::*,
+    process::signal::{PollHandle, Pollee},
+    vm::vmo::{CommitFlags, Vmo, VmoOptions},
+};
+
+/// A ring buffer map (`BPF_MAP_TYPE_RINGBUF`).
+///
+/// Programs submit records to the ring buffer, and user space consumes the records by mapping the
+/// ring buffer. The mapping consists of the consumer page, which contains the consumer position
+/// written by user space, the producer page, which contains the producer position, and the data
+/// area, which is mapped twice in a row so that records wrapping around the end are contiguous.
+/// Here the second copy is a real copy in the VMO, which is kept identical to the first one.
+///
+/// Reference: <https://docs.kernel.org/bpf/ringbuf.html>.
+pub struct RingBufMap {
+    attr: BpfMapAttr,
+    vmo: Arc<Vmo>,
+    /// The producer position, which is also written to the producer page.
+    producer_pos: SpinLock<u64>,
+    pollee: Pollee,
+}
+
+/// The size of a record header.
+const BPF_RINGBUF_HDR_SZ: usize = 8;
+/// The bit in the record length that marks records that are not committed.
+const BPF_RINGBUF_BUSY_BIT: u32 = 1 << 31;
+/// The bit in the record length that marks discarded records.
+const BPF_RINGBUF_DISCARD_BIT: u32 = 1 << 30;
+
+/// The flag not to notify user space of the new data.
+const BPF_RB_NO_WAKEUP: u64 = 1 << 0;
+/// The flag to notify user space of the new data, which is the default behavior here.
+const BPF_RB_FORCE_WAKEUP: u64 = 1 << 1;
+
+const CONSUMER_POS_OFFSET: usize = 0;
+const PRODUCER_POS_OFFSET: usize = PAGE_SIZE;
+const DATA_OFFSET: usize = 2 * PAGE_SIZE;
+
+impl RingBufMap {
+    pub(super) fn new(attr: BpfMapAttr) -> Result<Self> {
+        if attr.key_size != 0 || attr.value_size != 0 || attr.map_flags != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the map attributes are invalid");
+        }
+        let size = attr.max_entries as usize;
+        if !size.is_power_of_two() || size % PAGE_SIZE != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the ring buffer size is invalid");
+        }
+        if size > (u32::MAX / 4) as usize {
+            return_errno_with_message!(Errno::E2BIG, "the ring buffer is too large");
+        }
+
+        // Records are written when programs run, where we cannot sleep to commit pages. So all
+        // the pages are committed in advance.
+        let vmo_size = DATA_OFFSET + 2 * size;
+        let vmo = VmoOptions::new(vmo_size).alloc()?;
+        for page_idx in 0..vmo_size / PAGE_SIZE {
+            vmo.commit_on(page_idx, CommitFlags::empty())?;
+        }
+
+        Ok(Self {
+            attr,
+            vmo,
+            producer_pos: SpinLock::new(0),
+            pollee: Pollee::new(),
+        })
+    }
+
+    /// Returns the VMO to be mapped by user space.
+    pub(in crate::bpf) fn vmo(&self) -> &Arc<Vmo> {
+        &self.vmo
+    }
+
+    fn size(&self) -> usize {
+        self.attr.max_entries as usize
+    }
+
+    /// Reserves a record of `len` bytes and returns the position of the record data.
+    ///
+    /// This method returns `None` if there is not enough space.
+    pub(in crate::bpf) fn reserve(&self, len: usize) -> Option<u64> {
+        if len > self.size() - BPF_RINGBUF_HDR_SZ {
+            return None;
+        }
+        let record_len = (len + BPF_RINGBUF_HDR_SZ).align_up(8) as u64;
+
+        let mut producer_pos = self.producer_pos.lock();
+
+        let consumer_pos = self.read_val::<u64>(CONSUMER_POS_OFFSET);
+        fence(Ordering::Acquire);
+        let new_producer_pos = *producer_pos + record_len;
+        if new_producer_pos.wrapping_sub(consumer_pos) > self.size() as u64 {
+            return None;
+        }
+
+        let header_pos = *producer_pos;
+        let page_offset = (DATA_OFFSET + self.offset_of(header_pos)) / PAGE_SIZE;
+        self.write_header(header_pos, len as u32 | BPF_RINGBUF_BUSY_BIT, page_offset as u32);
+
+        // The header must be visible before the producer position.
+        fence(Ordering::Release);
+        self.write_val(PRODUCER_POS_OFFSET, &new_producer_pos);
+        *producer_pos = new_producer_pos;
+
+        Some(header_pos + BPF_RINGBUF_HDR_SZ as u64)
+    }
+
+    /// Commits the record at the position, which has been returned by [`Self::reserve`].
+    ///
+    /// If `is_discarded` is true, the record will be skipped by user space.
+    pub(in crate::bpf) fn commit(&self, pos: u64, is_discarded: bool, flags: u64) {
+        let header_pos = pos - BPF_RINGBUF_HDR_SZ as u64;
+        let header_offset = DATA_OFFSET + self.offset_of(header_pos);
+        let [len, page_offset] = self.read_val::<[u32; 2]>(header_offset);
+
+        let mut len = len & !BPF_RINGBUF_BUSY_BIT;
+        if is_discarded {
+            len |= BPF_RINGBUF_DISCARD_BIT;
+        }
+
+        // The record data must be visible before the header.
+        fence(Ordering::Release);
+        self.write_header(header_pos, len, page_offset);
+
+        if flags & BPF_RB_NO_WAKEUP == 0 {
+            self.pollee.notify(IoEvents::IN);
+        }
+    }
+
+    /// Submits a record with the data.
+    pub(in crate::bpf) fn output(&self, data: &[u8], flags: u64) -> Result<()> {
+        if flags & !(BPF_RB_NO_WAKEUP | BPF_RB_FORCE_WAKEUP) != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the flags are invalid");
+        }
+
+        let Some(pos) = self.reserve(data.len()) else {
+            return_errno_with_message!(Errno::EAGAIN, "the ring buffer is full");
+        };
+        self.write_data(pos, data);
+        self.commit(pos, false, flags);
+        Ok(())
+    }
+
+    /// Reads the data at the position in the data area.
+    pub(in crate::bpf) fn read_data(&self, pos: u64, buf: &mut [u8]) {
+        // Every byte is stored twice, so the data can be read from the first copy contiguously.
+        self.vmo
+            .read_bytes(DATA_OFFSET + self.offset_of(pos), buf)
+            .unwrap();
+    }
+
+    /// Writes the data at the position in the data area.
+    pub(in crate::bpf) fn write_data(&self, pos: u64, bytes: &[u8]) {
+        let offset = self.offset_of(pos);
+        let first_len = bytes.len().min(self.size() - offset);
+
+        for (offset, bytes) in [(offset, &bytes[..first_len]), (0, &bytes[first_len..])] {
+            self.write_bytes(DATA_OFFSET + offset, bytes);
+            self.write_bytes(DATA_OFFSET + self.size() + offset, bytes);
+        }
+    }
+
+    fn write_header(&self, header_pos: u64, len: u32, page_offset: u32) {
+        let mut header = [0u8; BPF_RINGBUF_HDR_SZ];
+        header[..4].copy_from_slice(&len.to_ne_bytes());
+        header[4..].copy_from_slice(&page_offset.to_ne_bytes());
+        self.write_data(header_pos, &header);
+    }
+
+    /// Returns the offset of the position in the first copy of the data area.
+    fn offset_of(&self, pos: u64) -> usize {
+        (pos & (self.size() as u64 - 1)) as usize
+    }
+
+    /// Polls the ring buffer, which is readable if there are records not consumed.
+    pub(in crate::bpf) fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        // User space consumes the records without notifying us, so the cached events may be
+        // outdated.
+        self.pollee.invalidate();
+        self.pollee.poll_with(mask, poller, || {
+            let consumer_pos = self.read_val::<u64>(CONSUMER_POS_OFFSET);
+            let producer_pos = self.read_val::<u64>(PRODUCER_POS_OFFSET);
+            if consumer_pos != producer_pos {
+                IoEvents::IN
+            } else {
+                IoEvents::empty()
+            }
+        })
+    }
+
+    fn read_val<T: Pod>(&self, offset: usize) -> T {
+        // The pages are committed, so reading them never fails.
+        self.vmo.read_val(offset).unwrap()
+    }
+
+    fn write_val<T: Pod>(&self, offset: usize, val: &T) {
+        // The pages are committed, so writing them never fails.
+        self.vmo.write_val(offset, val).unwrap();
+    }
+
+    fn write_bytes(&self, offset: usize, bytes: &[u8]) {
+        // The pages are committed, so writing them never fails.
+        self.vmo.write_bytes(offset, bytes).unwrap();
+    }
+}
+
+impl Debug for RingBufMap {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("RingBufMap")
+            .field("attr", &self.attr)
+            .finish_non_exhaustive()
+    }
+}
+
+impl BpfMap for RingBufMap {
+    fn attr(&self) -> &BpfMapAttr {
+        &self.attr
+    }
+
+    fn with_value(&self, _key: &[u8], _f: &mut dyn FnMut(&mut [u8])) -> Result<()> {
+        return_errno_with_message!(Errno::EOPNOTSUPP, "ring buffers have no elements");
+    }
+
+    fn update(&self, _key: &[u8], _value: &[u8], _mode: BpfUpdateMode) -> Result<()> {
+        return_errno_with_message!(Errno::EOPNOTSUPP, "ring buffers have no elements");
+    }
+
+    fn delete(&self, _key: &[u8]) -> Result<()> {
+        return_errno_with_message!(Errno::EOPNOTSUPP, "ring buffers have no elements");
+    }
+
+    fn get_next_key(&self, _key: Option<&[u8]>, _next_key: &mut [u8]) -> Result<()> {
+        return_errno_with_message!(Errno::EOPNOTSUPP, "ring buffers have no elements");
+    }
+
+    fn as_ringbuf(&self) -> Option<&RingBufMap> {
+        Some(self)
+    }
+}
@@ -0,0 +1,19 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! eBPF, which runs programs loaded by user space in the kernel.
+//!
+//! Programs are run by an interpreter that checks every memory access at runtime, so the verifier
+//! only checks that programs are well-formed.
+//!
+//! Reference: <https://docs.kernel.org/bpf/index.html>.
+
+pub use file::{BpfMapFile, BpfProgFile};
+pub use insn::{BPF_MAXINSNS, BpfInsn};
+pub use prog::{BpfProg, BpfProgType, SkBuff};
+
+mod file;
+mod insn;
+mod interpreter;
+pub mod map;
+mod prog;
+mod verifier;
@@ -0,0 +1,139 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! eBPF programs.
+
+use super::{
+    insn::{BPF_PSEUDO_MAP_FD, BpfInsn},
+    interpreter::Interpreter,
+    map::BpfMap,
+    verifier::check_program,
+};
+use crate::prelude::*;
+
+/// The type of an eBPF program, which determines where it can be attached.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/bpf.h#L922>.
+//
+// TODO: Support tracepoint programs once the kernel has tracepoints and perf events.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+pub enum BpfProgType {
+    SocketFilter = 1,
+}
+
+/// A loaded eBPF program.
+#[derive(Debug)]
+pub struct BpfProg {
+    prog_type: BpfProgType,
+    insns: Box<[BpfInsn]>,
+    /// The maps used by the program.
+    ///
+    /// The 64-bit immediate loads that refer to maps contain the indexes in this slice.
+    maps: Box<[Arc<dyn BpfMap>]>,
+}
+
+impl BpfProg {
+    /// Verifies and loads a program.
+    ///
+    /// The maps that the program refers to by their file descriptors are resolved with
+    /// `map_from_fd`.
+    pub fn load(
+        prog_type: BpfProgType,
+        mut insns: Box<[BpfInsn]>,
+        mut map_from_fd: impl FnMut(i32) -> Result<Arc<dyn BpfMap>>,
+    ) -> Result<Self> {
+        check_program(&insns, prog_type)?;
+
+        let mut maps: Vec<Arc<dyn BpfMap>> = Vec::new();
+        for insn in insns.iter_mut() {
+            if !insn.is_ld_imm64() || insn.src() != BPF_PSEUDO_MAP_FD {
+                continue;
+            }
+
+            let map = map_from_fd(insn.imm)?;
+            let index = match maps.iter().position(|used| Arc::ptr_eq(used, &map)) {
+                Some(index) => index,
+                None => {
+                    maps.push(map);
+                    maps.len() - 1
+                }
+            };
+            insn.imm = index as i32;
+        }
+
+        Ok(Self {
+            prog_type,
+            insns,
+            maps: maps.into_boxed_slice(),
+        })
+    }
+
+    /// Returns the type of the program.
+    pub fn prog_type(&self) -> BpfProgType {
+        self.prog_type
+    }
+
+    pub(super) fn insns(&self) -> &[BpfInsn] {
+        &self.insns
+    }
+
+    pub(super) fn maps(&self) -> &[Arc<dyn BpfMap>] {
+        &self.maps
+    }
+
+    /// Runs the socket filter on the packet and returns the number of bytes to keep.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the program is not a socket filter.
+    pub fn run_socket_filter(&self, packet: &SkBuff) -> u32 {
+        assert_eq!(self.prog_type, BpfProgType::SocketFilter);
+
+        let mut ctx = CSkBuff::new_zeroed();
+        ctx.len = packet.data.len() as u32;
+        ctx.pkt_type = packet.pkt_type as u32;
+        ctx.protocol = packet.protocol.to_be() as u32;
+        ctx.ingress_ifindex = packet.ifindex;
+        ctx.ifindex = packet.ifindex;
+
+        // A program that is aborted drops the packet.
+        Interpreter::new(self, ctx.as_mut_bytes(), packet.data)
+            .run()
+            .unwrap_or(0) as u32
+    }
+}
+
+/// A packet that a socket filter runs on.
+#[derive(Debug)]
+pub struct SkBuff<'a> {
+    /// The data that the socket receives.
+    pub data: &'a [u8],
+    /// The link-layer protocol in host byte order.
+    pub protocol: u16,
+    /// The packet type (`PACKET_HOST`, `PACKET_OUTGOING`, etc.).
+    pub pkt_type: u8,
+    /// The index of the interface that the packet is received on.
+    pub ifindex: u32,
+}
+
+/// The context of socket filters.
+///
+/// Only the fields before `tc_index` are filled, and the remaining fields are zero.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/bpf.h#L5748>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CSkBuff {
+    len: u32,
+    pkt_type: u32,
+    mark: u32,
+    queue_mapping: u32,
+    protocol: u32,
+    vlan_present: u32,
+    vlan_tci: u32,
+    vlan_proto: u32,
+    priority: u32,
+    ingress_ifindex: u32,
+    ifindex: u32,
+    rest: [u32; 37],
+}
@@ -0,0 +1,273 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The eBPF verifier.
+//!
+//! Unlike Linux, the verifier does not track the types and the ranges of the registers to prove
+//! that the memory accesses are safe, since the interpreter checks every memory access at runtime
+//! and limits the number of executed instructions. So the verifier only rejects programs that are
+//! malformed, e.g., programs that contain unknown instructions, jump out of bounds, write to the
+//! frame pointer, or call unknown helpers.
+
+use super::{insn::*, interpreter::BpfHelper, prog::BpfProgType};
+use crate::prelude::*;
+
+/// Checks whether the program is well-formed.
+pub(super) fn check_program(insns: &[BpfInsn], prog_type: BpfProgType) -> Result<()> {
+    if insns.is_empty() || insns.len() > BPF_MAXINSNS {
+        return_errno_with_message!(Errno::EINVAL, "the program length is invalid");
+    }
+
+    // The second halves of 64-bit immediate loads are not instructions, so they cannot be jumped
+    // to or executed.
+    let mut is_imm64_tail = vec![false; insns.len()];
+    let mut pc = 0;
+    while pc < insns.len() {
+        if insns[pc].is_ld_imm64() {
+            check_ld_imm64(insns, pc)?;
+            is_imm64_tail[pc + 1] = true;
+            pc += 2;
+        } else {
+            pc += 1;
+        }
+    }
+
+    for (pc, insn) in insns.iter().enumerate() {
+        if is_imm64_tail[pc] {
+            continue;
+        }
+
+        check_insn(insn, prog_type)?;
+
+        if let Some(target) = jump_target(insn, pc) {
+            let is_valid = usize::try_from(target)
+                .is_ok_and(|target| target < insns.len() && !is_imm64_tail[target]);
+            if !is_valid {
+                return_errno_with_message!(Errno::EINVAL, "the jump target is out of bounds");
+            }
+        }
+    }
+
+    // The program cannot fall through past the last instruction.
+    let last = insns.len() - 1;
+    let is_terminated = !is_imm64_tail[last]
+        && (insns[last].code == BPF_JMP | BPF_EXIT || insns[last].code == BPF_JMP | BPF_JA);
+    if !is_terminated {
+        return_errno_with_message!(Errno::EINVAL, "the program does not end with an exit");
+    }
+
+    Ok(())
+}
+
+fn check_ld_imm64(insns: &[BpfInsn], pc: usize) -> Result<()> {
+    let insn = &insns[pc];
+    let Some(next) = insns.get(pc + 1) else {
+        return_errno_with_message!(Errno::EINVAL, "the 64-bit immediate load is incomplete");
+    };
+    if next.code != 0 || next.dst() != 0 || next.src() != 0 || next.off != 0 {
+        return_errno_with_message!(Errno::EINVAL, "the 64-bit immediate load is invalid");
+    }
+    if insn.off != 0 || (insn.src() != 0 && insn.src() != BPF_PSEUDO_MAP_FD) {
+        return_errno_with_message!(Errno::EINVAL, "the 64-bit immediate load is invalid");
+    }
+    if insn.src() == BPF_PSEUDO_MAP_FD && next.imm != 0 {
+        return_errno_with_message!(Errno::EINVAL, "the map reference is invalid");
+    }
+    check_dst_reg(insn)
+}
+
+/// Returns the target of the jump instruction, if it is.
+fn jump_target(insn: &BpfInsn, pc: usize) -> Option<isize> {
+    if insn.class() != BPF_JMP && insn.class() != BPF_JMP32 {
+        return None;
+    }
+    match insn.code & 0xf0 {
+        BPF_CALL | BPF_EXIT => None,
+        _ => Some(pc as isize + 1 + insn.off as isize),
+    }
+}
+
+fn check_insn(insn: &BpfInsn, prog_type: BpfProgType) -> Result<()> {
+    let code = insn.code;
+
+    match insn.class() {
+        BPF_ALU | BPF_ALU64 => check_alu(insn),
+        BPF_JMP | BPF_JMP32 => check_jmp(insn, prog_type),
+        BPF_LDX => {
+            let mode = code & 0xe0;
+            let is_valid = mode == BPF_MEM || (mode == BPF_MEMSX && code & 0x18 != BPF_DW);
+            if !is_valid || insn.imm != 0 {
+                return_errno_with_message!(Errno::EINVAL, "the load instruction is invalid");
+            }
+            check_src_reg(insn)?;
+            check_dst_reg(insn)
+        }
+        BPF_ST => {
+            if code & 0xe0 != BPF_MEM || insn.src() != 0 {
+                return_errno_with_message!(Errno::EINVAL, "the store instruction is invalid");
+            }
+            check_reg(insn.dst())
+        }
+        BPF_STX => {
+            match code & 0xe0 {
+                BPF_MEM if insn.imm == 0 => (),
+                BPF_ATOMIC if code & 0x18 == BPF_W || code & 0x18 == BPF_DW => {
+                    check_atomic_op(insn.imm)?
+                }
+                _ => return_errno_with_message!(Errno::EINVAL, "the store instruction is invalid"),
+            }
+            check_src_reg(insn)?;
+            check_reg(insn.dst())
+        }
+        BPF_LD => {
+            // The 64-bit immediate loads have been checked.
+            let mode = code & 0xe0;
+            if (mode != BPF_ABS && mode != BPF_IND) || code & 0x18 == BPF_DW {
+                return_errno_with_message!(Errno::EINVAL, "the load instruction is invalid");
+            }
+            // Legacy packet loads are only available to socket filters.
+            if prog_type != BpfProgType::SocketFilter || insn.dst() != 0 || insn.off != 0 {
+                return_errno_with_message!(Errno::EINVAL, "the packet load is invalid");
+            }
+            if mode == BPF_ABS && insn.src() != 0 {
+                return_errno_with_message!(Errno::EINVAL, "the packet load is invalid");
+            }
+            check_src_reg(insn)
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn check_alu(insn: &BpfInsn) -> Result<()> {
+    let is_64 = insn.class() == BPF_ALU64;
+    let is_reg_src = insn.code & BPF_X != 0;
+    let op = insn.code & 0xf0;
+
+    let is_valid = match op {
+        BPF_ADD | BPF_SUB | BPF_MUL | BPF_OR | BPF_AND | BPF_LSH | BPF_RSH | BPF_XOR
+        | BPF_ARSH => insn.off == 0,
+        BPF_DIV | BPF_MOD => insn.off == 0 || insn.off == 1,
+        BPF_NEG => !is_reg_src && insn.off == 0 && insn.imm == 0,
+        // The offset specifies the size of the sign extension.
+        BPF_MOV if is_reg_src => match insn.off {
+            0 | 8 | 16 => true,
+            32 => is_64,
+            _ => false,
+        },
+        BPF_MOV => insn.off == 0,
+        // Unconditional byte swaps do not support the source bit.
+        BPF_END => {
+            insn.off == 0 && matches!(insn.imm, 16 | 32 | 64) && !(is_64 && is_reg_src)
+        }
+        _ => false,
+    };
+    if !is_valid {
+        return_errno_with_message!(Errno::EINVAL, "the arithmetic instruction is invalid");
+    }
+
+    if is_reg_src && op != BPF_END {
+        if insn.imm != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the arithmetic instruction is invalid");
+        }
+        check_src_reg(insn)?;
+    } else {
+        if insn.src() != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the arithmetic instruction is invalid");
+        }
+        match op {
+            BPF_DIV | BPF_MOD if insn.imm == 0 => {
+                return_errno_with_message!(Errno::EINVAL, "the program divides by zero");
+            }
+            BPF_LSH | BPF_RSH | BPF_ARSH => {
+                let width = if is_64 { 64 } else { 32 };
+                if !(0..width).contains(&insn.imm) {
+                    return_errno_with_message!(Errno::EINVAL, "the shift amount is too large");
+                }
+            }
+            _ => (),
+        }
+    }
+
+    check_dst_reg(insn)
+}
+
+fn check_jmp(insn: &BpfInsn, prog_type: BpfProgType) -> Result<()> {
+    let is_32 = insn.class() == BPF_JMP32;
+    let is_reg_src = insn.code & BPF_X != 0;
+
+    match insn.code & 0xf0 {
+        BPF_JA | BPF_EXIT => {
+            if is_32 || is_reg_src || insn.dst() != 0 || insn.src() != 0 || insn.imm != 0 {
+                return_errno_with_message!(Errno::EINVAL, "the jump instruction is invalid");
+            }
+            if insn.code & 0xf0 == BPF_EXIT && insn.off != 0 {
+                return_errno_with_message!(Errno::EINVAL, "the exit instruction is invalid");
+            }
+            Ok(())
+        }
+        BPF_CALL => {
+            if is_32 || is_reg_src || insn.dst() != 0 || insn.off != 0 {
+                return_errno_with_message!(Errno::EINVAL, "the call instruction is invalid");
+            }
+            // TODO: Support calling BPF functions and kernel functions.
+            if insn.src() != 0 {
+                return_errno_with_message!(Errno::EINVAL, "only helpers can be called");
+            }
+            let is_allowed = BpfHelper::try_from(insn.imm)
+                .is_ok_and(|helper| helper.is_allowed_for(prog_type));
+            if !is_allowed {
+                return_errno_with_message!(Errno::EINVAL, "the helper is unknown or not allowed");
+            }
+            Ok(())
+        }
+        BPF_JEQ | BPF_JGT | BPF_JGE | BPF_JSET | BPF_JNE | BPF_JSGT | BPF_JSGE | BPF_JLT
+        | BPF_JLE | BPF_JSLT | BPF_JSLE => {
+            if is_reg_src {
+                if insn.imm != 0 {
+                    return_errno_with_message!(Errno::EINVAL, "the jump instruction is invalid");
+                }
+                check_src_reg(insn)?;
+            } else if insn.src() != 0 {
+                return_errno_with_message!(Errno::EINVAL, "the jump instruction is invalid");
+            }
+            check_reg(insn.dst())
+        }
+        _ => return_errno_with_message!(Errno::EINVAL, "the jump instruction is invalid"),
+    }
+}
+
+fn check_atomic_op(op: i32) -> Result<()> {
+    let is_valid = match op {
+        BPF_XCHG | BPF_CMPXCHG => true,
+        // The other operations are arithmetic operations with an optional fetch flag.
+        _ => {
+            let alu_op = op & !BPF_FETCH;
+            [BPF_ADD, BPF_OR, BPF_AND, BPF_XOR]
+                .iter()
+                .any(|&valid_op| alu_op == valid_op as i32)
+        }
+    };
+    if !is_valid {
+        return_errno_with_message!(Errno::EINVAL, "the atomic operation is invalid");
+    }
+    Ok(())
+}
+
+fn check_reg(reg: usize) -> Result<()> {
+    if reg >= MAX_BPF_REG {
+        return_errno_with_message!(Errno::EINVAL, "the register is invalid");
+    }
+    Ok(())
+}
+
+fn check_src_reg(insn: &BpfInsn) -> Result<()> {
+    check_reg(insn.src())
+}
+
+/// Checks the destination register, which is written by the instruction.
+fn check_dst_reg(insn: &BpfInsn) -> Result<()> {
+    check_reg(insn.dst())?;
+    if insn.dst() == BPF_REG_FP {
+        return_errno_with_message!(Errno::EACCES, "the frame pointer is read-only");
+    }
+    Ok(())
+}
@@ -0,0 +1,87 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The CPU frequency scaling governors.
+//!
+//! Reference: <https://docs.kernel.org/admin-guide/pm/cpufreq.html#generic-scaling-governors>
+
+/// The utilization of a fully busy CPU.
+pub(super) const UTIL_SCALE: u32 = 1024;
+
+/// The utilization above which the `ondemand` governor selects the maximum frequency.
+const ONDEMAND_UP_THRESHOLD: u32 = UTIL_SCALE * 80 / 100;
+
+/// A governor that selects the frequency of a CPU.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Governor {
+    /// Always selects the maximum frequency.
+    Performance,
+    /// Always selects the minimum frequency.
+    Powersave,
+    /// Selects the maximum frequency if the CPU is mostly busy, or a frequency proportional to
+    /// the utilization otherwise.
+    Ondemand,
+    /// Selects a frequency proportional to the utilization with some headroom.
+    Schedutil,
+}
+
+impl Governor {
+    /// All the available governors.
+    pub const ALL: [Self; 4] = [
+        Self::Performance,
+        Self::Powersave,
+        Self::Ondemand,
+        Self::Schedutil,
+    ];
+
+    /// Returns the name of the governor.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Performance => "performance",
+            Self::Powersave => "powersave",
+            Self::Ondemand => "ondemand",
+            Self::Schedutil => "schedutil",
+        }
+    }
+
+    /// Looks up a governor by its name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|governor| governor.name() == name)
+    }
+
+    /// Returns whether the governor depends on the utilization of the CPU.
+    pub(super) fn is_dynamic(self) -> bool {
+        matches!(self, Self::Ondemand | Self::Schedutil)
+    }
+
+    /// Selects the frequency in kHz.
+    ///
+    /// `util` is the utilization of the CPU in the last sampling period, which ranges from 0 to
+    /// [`UTIL_SCALE`]. `cpuinfo_min` and `cpuinfo_max` are the frequency range supported by the
+    /// hardware, while `scaling_min` and `scaling_max` are the limits set by the user. The
+    /// selected frequency is within the limits.
+    pub(super) fn select_freq(
+        self,
+        util: u32,
+        (cpuinfo_min, cpuinfo_max): (u32, u32),
+        (scaling_min, scaling_max): (u32, u32),
+    ) -> u32 {
+        let freq = match self {
+            Self::Performance => scaling_max,
+            Self::Powersave => scaling_min,
+            Self::Ondemand if util > ONDEMAND_UP_THRESHOLD => scaling_max,
+            Self::Ondemand => cpuinfo_min + scale(cpuinfo_max - cpuinfo_min, util, UTIL_SCALE),
+            // Linux uses 1.25 as the headroom so that the utilization can still grow at the
+            // selected frequency.
+            Self::Schedutil => scale(cpuinfo_max + (cpuinfo_max >> 2), util, UTIL_SCALE),
+        };
+
+        freq.clamp(scaling_min, scaling_max)
+    }
+}
+
+/// Computes `value * numerator / denominator` without overflows.
+fn scale(value: u32, numerator: u32, denominator: u32) -> u32 {
+    (value as u64 * numerator as u64 / denominator as u64) as u32
+}
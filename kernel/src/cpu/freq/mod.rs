@@ -0,0 +1,271 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! CPU frequency scaling (cpufreq).
+//!
+//! Each CPU has a policy, which limits the frequency of the CPU and selects the frequency with a
+//! governor (see [`Governor`]). The utilization of the CPU is sampled from the scheduler at the
+//! timer ticks of the CPU, so that the frequency can be selected and set on the CPU itself. The
+//! frequency is set by the CPU frequency scaling driver of the architecture (see
+//! [`CpufreqDriver`]).
+//!
+//! The policy of CPU N is exposed at `/sys/devices/system/cpu/cpufreq/policyN`, which can also
+//! be found via the `/sys/devices/system/cpu/cpuN/cpufreq` symlink.
+//!
+//! Reference: <https://docs.kernel.org/admin-guide/pm/cpufreq.html>
+
+mod governor;
+
+use ostd::{
+    arch::{read_tsc, tsc_freq},
+    cpu::{CpuId, PinCurrentCpu},
+    irq::{DisabledLocalIrqGuard, disable_local},
+    sync::LocalIrqDisabled,
+    util::id_set::Id,
+};
+use spin::Once;
+
+use self::governor::{Governor, UTIL_SCALE};
+use crate::{
+    fs::sysfs::{KObject, ShowFn, StoreFn},
+    prelude::*,
+};
+
+/// A driver that scales the frequency of the CPUs.
+///
+/// All frequencies are measured in kHz.
+pub trait CpufreqDriver: Send + Sync {
+    /// Returns the name of the driver.
+    fn name(&self) -> &'static str;
+
+    /// Returns the minimum frequency supported by the CPUs.
+    fn min_freq(&self) -> u32;
+
+    /// Returns the maximum frequency supported by the CPUs.
+    fn max_freq(&self) -> u32;
+
+    /// Returns the current frequency of the current CPU.
+    fn current_freq(&self, irq_guard: &DisabledLocalIrqGuard) -> u32;
+
+    /// Sets the frequency of the current CPU to the lowest supported frequency at or above
+    /// `freq`, returning the frequency that is set.
+    fn set_freq(&self, freq: u32, irq_guard: &DisabledLocalIrqGuard) -> u32;
+}
+
+/// The governor that is used by default.
+const DEFAULT_GOVERNOR: Governor = Governor::Schedutil;
+
+/// The period to sample the utilization of the CPU, in milliseconds.
+const SAMPLING_PERIOD_MS: u64 = 10;
+
+/// The policy that scales the frequency of a CPU.
+struct CpufreqPolicy {
+    governor: Governor,
+    /// The minimum frequency set by the user.
+    scaling_min: u32,
+    /// The maximum frequency set by the user.
+    scaling_max: u32,
+    /// The frequency that is currently set.
+    cur_freq: u32,
+    /// The utilization of the CPU in the last sampling period.
+    util: u32,
+    /// The TSC value at the last sample.
+    last_sample_time: u64,
+    /// The busy time of the CPU at the last sample. See [`crate::sched::cpu_busy_time`].
+    last_busy_time: u64,
+    /// Whether the frequency should be selected again at the next tick.
+    needs_update: bool,
+}
+
+static DRIVER: Once<&'static dyn CpufreqDriver> = Once::new();
+
+/// The policies of the CPUs.
+///
+/// We use the `LocalIrqDisabled` marker since the policies are updated in the timer interrupts.
+static POLICIES: Once<Box<[SpinLock<CpufreqPolicy, LocalIrqDisabled>]>> = Once::new();
+
+pub fn init() {
+    let Some(driver) = crate::arch::cpufreq::driver() else {
+        return;
+    };
+
+    let (min_freq, max_freq) = (driver.min_freq(), driver.max_freq());
+    let new_policy = |_| {
+        SpinLock::new(CpufreqPolicy {
+            governor: DEFAULT_GOVERNOR,
+            scaling_min: min_freq,
+            scaling_max: max_freq,
+            cur_freq: max_freq,
+            util: 0,
+            last_sample_time: 0,
+            last_busy_time: 0,
+            needs_update: true,
+        })
+    };
+    POLICIES.call_once(|| ostd::cpu::all_cpus().map(new_policy).collect());
+    DRIVER.call_once(|| driver);
+
+    info!(
+        "[kernel] CPU frequency scaling driver: {} ({}-{} kHz)",
+        driver.name(),
+        min_freq,
+        max_freq
+    );
+}
+
+pub fn init_on_each_cpu() {
+    let (Some(driver), Some(policies)) = (DRIVER.get(), POLICIES.get()) else {
+        return;
+    };
+
+    let irq_guard = disable_local();
+    let cpu = irq_guard.current_cpu();
+    let mut policy = policies[cpu.as_usize()].lock();
+    policy.cur_freq = driver.current_freq(&irq_guard);
+    policy.last_sample_time = read_tsc();
+    policy.last_busy_time = crate::sched::cpu_busy_time(cpu);
+    drop(policy);
+
+    ostd::timer::register_callback_on_cpu(update_freq_on_tick);
+}
+
+/// Samples the utilization of the current CPU and selects its frequency if needed.
+fn update_freq_on_tick() {
+    let irq_guard = disable_local();
+    let cpu = irq_guard.current_cpu();
+    let driver = *DRIVER.get().unwrap();
+    let mut policy = POLICIES.get().unwrap()[cpu.as_usize()].lock();
+
+    let now = read_tsc();
+    let elapsed = now - policy.last_sample_time;
+    if elapsed >= tsc_freq() * SAMPLING_PERIOD_MS / 1000 {
+        let busy_time = crate::sched::cpu_busy_time(cpu);
+        let busy = busy_time - policy.last_busy_time;
+        policy.util = (busy * UTIL_SCALE as u64 / elapsed).min(UTIL_SCALE as u64) as u32;
+        policy.last_sample_time = now;
+        policy.last_busy_time = busy_time;
+
+        if policy.governor.is_dynamic() {
+            policy.needs_update = true;
+        }
+    }
+
+    if !policy.needs_update {
+        return;
+    }
+    policy.needs_update = false;
+
+    let freq = policy.governor.select_freq(
+        policy.util,
+        (driver.min_freq(), driver.max_freq()),
+        (policy.scaling_min, policy.scaling_max),
+    );
+    if freq != policy.cur_freq {
+        policy.cur_freq = driver.set_freq(freq, &irq_guard);
+    }
+}
+
+/// Updates the policy of the CPU.
+///
+/// The frequency of the CPU will be selected again at its next tick.
+fn update_policy(cpu: CpuId, f: impl FnOnce(&mut CpufreqPolicy)) {
+    let mut policy = POLICIES.get().unwrap()[cpu.as_usize()].lock();
+    f(&mut policy);
+    policy.needs_update = true;
+}
+
+/// Creates the kernel object of the `/sys/devices/system/cpu/cpufreq` directory.
+///
+/// This method will return `None` if the CPU frequency cannot be scaled.
+pub fn new_cpufreq_kobject() -> Option<Arc<KObject>> {
+    POLICIES.get()?;
+
+    let cpufreq = KObject::new_dir("cpufreq");
+    for cpu in ostd::cpu::all_cpus() {
+        cpufreq.add_child(new_policy_kobject(cpu)).unwrap();
+    }
+
+    Some(cpufreq)
+}
+
+fn new_policy_kobject(cpu: CpuId) -> Arc<KObject> {
+    let driver = *DRIVER.get().unwrap();
+    let policy = &POLICIES.get().unwrap()[cpu.as_usize()];
+
+    let show_cpus = move || format!("{}\n", cpu.as_usize());
+    let show_freq = move |freq: u32| Box::new(move || format!("{}\n", freq)) as ShowFn;
+    let show_policy =
+        move |f: fn(&CpufreqPolicy) -> String| Box::new(move || f(&policy.lock())) as ShowFn;
+    let available_governors = Governor::ALL
+        .iter()
+        .map(|governor| governor.name())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let attrs = vec![
+        ("affected_cpus", Box::new(show_cpus) as ShowFn),
+        ("related_cpus", Box::new(show_cpus) as ShowFn),
+        ("cpuinfo_min_freq", show_freq(driver.min_freq())),
+        ("cpuinfo_max_freq", show_freq(driver.max_freq())),
+        (
+            "scaling_driver",
+            Box::new(move || format!("{}\n", driver.name())) as ShowFn,
+        ),
+        (
+            "scaling_available_governors",
+            Box::new(move || format!("{}\n", available_governors)) as ShowFn,
+        ),
+        (
+            "scaling_governor",
+            show_policy(|policy| format!("{}\n", policy.governor.name())),
+        ),
+        (
+            "scaling_cur_freq",
+            show_policy(|policy| format!("{}\n", policy.cur_freq)),
+        ),
+        (
+            "scaling_min_freq",
+            show_policy(|policy| format!("{}\n", policy.scaling_min)),
+        ),
+        (
+            "scaling_max_freq",
+            show_policy(|policy| format!("{}\n", policy.scaling_max)),
+        ),
+    ];
+
+    let store_governor = Box::new(move |value: &str| {
+        let governor =
+            Governor::from_name(value.trim()).ok_or(aster_systree::Error::InvalidOperation)?;
+        update_policy(cpu, |policy| policy.governor = governor);
+        Ok(())
+    }) as StoreFn;
+    let store_min_freq = Box::new(move |value: &str| {
+        let freq = parse_freq(value)?;
+        update_policy(cpu, |policy| {
+            policy.scaling_min = freq.clamp(driver.min_freq(), policy.scaling_max);
+        });
+        Ok(())
+    }) as StoreFn;
+    let store_max_freq = Box::new(move |value: &str| {
+        let freq = parse_freq(value)?;
+        update_policy(cpu, |policy| {
+            policy.scaling_max = freq.clamp(policy.scaling_min, driver.max_freq());
+        });
+        Ok(())
+    }) as StoreFn;
+
+    let stores = vec![
+        ("scaling_governor", store_governor),
+        ("scaling_min_freq", store_min_freq),
+        ("scaling_max_freq", store_max_freq),
+    ];
+
+    KObject::new_with_stores(format!("policy{}", cpu.as_usize()), attrs, stores)
+}
+
+/// Parses a frequency in kHz written to a sysfs attribute.
+fn parse_freq(value: &str) -> aster_systree::Result<u32> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| aster_systree::Error::InvalidOperation)
+}
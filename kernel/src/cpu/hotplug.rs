@@ -0,0 +1,215 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! CPU hotplug.
+//!
+//! A CPU can be taken offline (see [`cpu_down`]) and brought online again (see [`cpu_up`]).
+//!
+//! When a CPU is taken offline, the runnable tasks on it are migrated to the online CPUs, and the
+//! interrupts delivered to it are redirected to an online CPU. Then the CPU runs its idle task
+//! only, which keeps the CPU halted until it is brought online again. The per-CPU subsystems can
+//! adapt to the changes via the callbacks registered with [`register_callbacks`], e.g., to
+//! migrate the per-CPU timers.
+//!
+//! The CPU is not powered off, so it still responds to inter-processor interrupts (e.g., TLB
+//! shootdowns) while it is offline.
+//!
+//! The bootstrap processor (BSP) cannot be taken offline.
+//!
+//! Reference: <https://docs.kernel.org/core-api/cpu_hotplug.html>
+
+use core::{sync::atomic::Ordering, time::Duration};
+
+use ostd::{
+    cpu::{AtomicCpuSet, CpuId, CpuSet},
+    irq::IrqLine,
+    sync::WaitQueue,
+    util::id_set::Id,
+};
+use spin::Once;
+
+use crate::prelude::*;
+
+/// The callbacks that adapt a per-CPU subsystem to CPU hotplug.
+#[derive(Clone, Copy)]
+pub struct CpuHotplugCallbacks {
+    /// The name of the subsystem.
+    pub name: &'static str,
+    /// Called after a CPU is brought online.
+    pub online: fn(CpuId),
+    /// Called after a CPU is taken offline.
+    ///
+    /// The tasks on the CPU have been migrated away when the callback is called. The callback
+    /// runs on another CPU.
+    pub offline: fn(CpuId),
+}
+
+/// Registers the callbacks that adapt a per-CPU subsystem to CPU hotplug.
+///
+/// The `online` callbacks are called in the order of their registration, while the `offline`
+/// callbacks are called in the reverse order.
+pub fn register_callbacks(callbacks: CpuHotplugCallbacks) {
+    CALLBACKS.lock().push(callbacks);
+}
+
+static CALLBACKS: Mutex<Vec<CpuHotplugCallbacks>> = Mutex::new(Vec::new());
+
+/// The lock that serializes the CPU hotplug operations.
+static HOTPLUG_LOCK: Mutex<()> = Mutex::new(());
+
+static ONLINE_CPUS: Once<AtomicCpuSet> = Once::new();
+
+fn online_cpu_set() -> &'static AtomicCpuSet {
+    ONLINE_CPUS.call_once(|| AtomicCpuSet::new(CpuSet::new_full()))
+}
+
+/// Returns whether the CPU is online.
+pub fn is_cpu_online(cpu: CpuId) -> bool {
+    online_cpu_set().contains(cpu, Ordering::Acquire)
+}
+
+/// Returns the set of the online CPUs.
+pub fn online_cpus() -> CpuSet {
+    online_cpu_set().load(Ordering::Acquire)
+}
+
+/// Returns whether the CPU can be taken offline.
+pub fn is_cpu_hotpluggable(cpu: CpuId) -> bool {
+    cpu != CpuId::bsp()
+}
+
+/// The interval to wait for the current task on an offline CPU to be preempted.
+const MIGRATION_WAIT_INTERVAL: Duration = Duration::from_millis(1);
+/// The maximum number of intervals to wait for the tasks to be migrated.
+const MIGRATION_MAX_RETRIES: usize = 1000;
+
+/// Takes the CPU offline.
+///
+/// This function does nothing if the CPU is already offline.
+///
+/// # Errors
+///
+/// This function fails with
+///  - [`Errno::EINVAL`], if the CPU cannot be taken offline (e.g., the BSP);
+///  - [`Errno::EBUSY`], if the CPU is the last online CPU, or if the current task on the CPU
+///    cannot be migrated in time (e.g., a kernel thread that never yields).
+pub fn cpu_down(cpu: CpuId) -> Result<()> {
+    if !is_cpu_hotpluggable(cpu) {
+        return_errno_with_message!(Errno::EINVAL, "the CPU cannot be taken offline");
+    }
+
+    let _guard = HOTPLUG_LOCK.lock();
+
+    if !is_cpu_online(cpu) {
+        return Ok(());
+    }
+    if online_cpus().count() == 1 {
+        return_errno_with_message!(Errno::EBUSY, "the last online CPU cannot be taken offline");
+    }
+
+    online_cpu_set().remove(cpu, Ordering::Release);
+    crate::sched::set_cpu_online(cpu, false);
+
+    if !migrate_tasks_from(cpu) {
+        crate::sched::set_cpu_online(cpu, true);
+        online_cpu_set().add(cpu, Ordering::Release);
+        return_errno_with_message!(Errno::EBUSY, "the tasks on the CPU cannot be migrated");
+    }
+
+    migrate_irqs_from(cpu);
+
+    let callbacks = CALLBACKS.lock().clone();
+    for callbacks in callbacks.iter().rev() {
+        debug!(
+            "[kernel] CPU #{} offline: {}",
+            cpu.as_usize(),
+            callbacks.name
+        );
+        (callbacks.offline)(cpu);
+    }
+
+    info!("[kernel] CPU #{} is offline", cpu.as_usize());
+
+    Ok(())
+}
+
+/// Brings the CPU online.
+///
+/// This function does nothing if the CPU is already online.
+pub fn cpu_up(cpu: CpuId) -> Result<()> {
+    let _guard = HOTPLUG_LOCK.lock();
+
+    if is_cpu_online(cpu) {
+        return Ok(());
+    }
+
+    crate::sched::set_cpu_online(cpu, true);
+    online_cpu_set().add(cpu, Ordering::Release);
+
+    let callbacks = CALLBACKS.lock().clone();
+    for callbacks in callbacks.iter() {
+        debug!(
+            "[kernel] CPU #{} online: {}",
+            cpu.as_usize(),
+            callbacks.name
+        );
+        (callbacks.online)(cpu);
+    }
+
+    info!("[kernel] CPU #{} is online", cpu.as_usize());
+
+    Ok(())
+}
+
+/// Migrates the runnable tasks on the offline CPU to the online CPUs.
+///
+/// Returns whether the CPU is running its idle task only.
+fn migrate_tasks_from(cpu: CpuId) -> bool {
+    let wait_queue = WaitQueue::new();
+
+    for _ in 0..MIGRATION_MAX_RETRIES {
+        if crate::sched::migrate_tasks_from(cpu) {
+            return true;
+        }
+
+        // The current task on the CPU will be preempted at the next tick. Wait for it.
+        let _ =
+            wait_queue.wait_until_or_timeout(|| -> Option<()> { None }, &MIGRATION_WAIT_INTERVAL);
+    }
+
+    false
+}
+
+/// Redirects the interrupts delivered to the offline CPU to an online CPU.
+fn migrate_irqs_from(cpu: CpuId) {
+    let Some(target_cpu) = online_cpus().iter().next() else {
+        return;
+    };
+
+    for irq_line in (0..=u8::MAX).filter_map(IrqLine::lookup) {
+        if irq_line.affinity() != cpu {
+            continue;
+        }
+
+        if let Err(err) = irq_line.set_affinity(target_cpu) {
+            warn!(
+                "[kernel] failed to migrate IRQ {} from CPU #{}: {:?}",
+                irq_line.num(),
+                cpu.as_usize(),
+                err
+            );
+        }
+    }
+}
+
+/// Returns the online CPUs among the allowed CPUs.
+///
+/// If none of the allowed CPUs are online, all the online CPUs are returned, i.e., the affinity
+/// is broken.
+pub fn online_cpus_in(allowed: &CpuSet) -> CpuSet {
+    let mut cpus = CpuSet::new_empty();
+    for cpu in allowed.iter().filter(|cpu| is_cpu_online(*cpu)) {
+        cpus.add(cpu);
+    }
+
+    if cpus.is_empty() { online_cpus() } else { cpus }
+}
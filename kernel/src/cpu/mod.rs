@@ -1,5 +1,8 @@
 // SPDX-License-Identifier: MPL-2.0
 
+pub mod freq;
+pub mod hotplug;
+
 /// A trait that describes the Linux system call convention (ABI) for the user context.
 pub trait LinuxAbi {
     /// Gets the system call number.
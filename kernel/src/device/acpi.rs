@@ -0,0 +1,220 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The ACPI thermal zones and batteries.
+//!
+//! The thermal zones are exposed in `/sys/class/thermal` and the batteries are exposed in
+//! `/sys/class/power_supply`. The power button is an input device, so it is exposed by evdev.
+//!
+//! Reference: <https://www.kernel.org/doc/Documentation/ABI/testing/sysfs-class-power>
+
+use aster_acpi::{Battery, ChargingState, PowerUnit, ThermalZone};
+
+use crate::{
+    fs::sysfs::{self, ShowFn, SysDevice},
+    prelude::*,
+};
+
+pub(super) fn init_in_first_kthread() {
+    for (index, thermal_zone) in aster_acpi::thermal_zones().iter().enumerate() {
+        add_sys_device(new_thermal_zone_device(index, thermal_zone));
+    }
+
+    for battery in aster_acpi::batteries() {
+        add_sys_device(new_battery_device(battery));
+    }
+}
+
+fn add_sys_device(device: SysDevice) {
+    let name = device.name.clone();
+    if let Err(err) = sysfs::add_device(device, None) {
+        warn!(
+            "failed to add the ACPI device '{}' to sysfs: {:?}",
+            name, err
+        );
+    }
+}
+
+/// Formats the value, or returns an empty string if the value is unknown.
+fn format_or_empty<T: core::fmt::Display>(value: Option<T>) -> String {
+    value.map_or_else(String::new, |value| format!("{}\n", value))
+}
+
+fn new_thermal_zone_device(index: usize, thermal_zone: &'static ThermalZone) -> SysDevice {
+    let mut attrs = vec![
+        ("type", Box::new(|| "acpitz\n".to_string()) as ShowFn),
+        (
+            "temp",
+            Box::new(move || format_or_empty(thermal_zone.temperature())) as ShowFn,
+        ),
+    ];
+
+    // The trip points are numbered from zero in the order of the critical and the passive ones.
+    let trip_points = [
+        ("critical", thermal_zone.critical_temperature()),
+        ("passive", thermal_zone.passive_temperature()),
+    ];
+    let trip_point_attrs = [
+        ("trip_point_0_type", "trip_point_0_temp"),
+        ("trip_point_1_type", "trip_point_1_temp"),
+    ];
+    let valid_trip_points = trip_points
+        .into_iter()
+        .filter_map(|(type_, temp)| Some((type_, temp?)));
+    for ((type_attr, temp_attr), (type_, temp)) in
+        trip_point_attrs.into_iter().zip(valid_trip_points)
+    {
+        attrs.push((
+            type_attr,
+            Box::new(move || format!("{}\n", type_)) as ShowFn,
+        ));
+        attrs.push((temp_attr, Box::new(move || format!("{}\n", temp)) as ShowFn));
+    }
+
+    SysDevice {
+        class: "thermal",
+        name: format!("thermal_zone{}", index),
+        dev: None,
+        uevent_vars: Vec::new(),
+        attrs,
+    }
+}
+
+fn new_battery_device(battery: &'static Battery) -> SysDevice {
+    // The unit cannot be changed without replacing the battery, so it is decided at boot.
+    let power_unit = battery
+        .info()
+        .map_or(PowerUnit::MilliWatt, |info| info.power_unit);
+    let (now_attr, full_attr, full_design_attr, rate_attr) = match power_unit {
+        PowerUnit::MilliWatt => (
+            "energy_now",
+            "energy_full",
+            "energy_full_design",
+            "power_now",
+        ),
+        PowerUnit::MilliAmpere => (
+            "charge_now",
+            "charge_full",
+            "charge_full_design",
+            "current_now",
+        ),
+    };
+
+    let attrs = vec![
+        ("type", Box::new(|| "Battery\n".to_string()) as ShowFn),
+        (
+            "present",
+            Box::new(move || format!("{}\n", battery.is_present() as u8)) as ShowFn,
+        ),
+        (
+            "status",
+            Box::new(move || battery_status_of(battery).to_string() + "\n") as ShowFn,
+        ),
+        (
+            "capacity",
+            Box::new(move || {
+                let capacity = battery
+                    .status()
+                    .zip(battery.info())
+                    .and_then(|(status, info)| {
+                        let remaining = status.remaining_capacity? as u64;
+                        let full = info.last_full_capacity.filter(|full| *full != 0)? as u64;
+                        Some((remaining * 100 / full).min(100))
+                    });
+                format_or_empty(capacity)
+            }) as ShowFn,
+        ),
+        (
+            now_attr,
+            Box::new(move || {
+                format_micro(
+                    battery
+                        .status()
+                        .and_then(|status| status.remaining_capacity),
+                )
+            }) as ShowFn,
+        ),
+        (
+            full_attr,
+            Box::new(move || format_micro(battery.info().and_then(|info| info.last_full_capacity)))
+                as ShowFn,
+        ),
+        (
+            full_design_attr,
+            Box::new(move || format_micro(battery.info().and_then(|info| info.design_capacity)))
+                as ShowFn,
+        ),
+        (
+            rate_attr,
+            Box::new(move || format_micro(battery.status().and_then(|status| status.rate)))
+                as ShowFn,
+        ),
+        (
+            "voltage_now",
+            Box::new(move || format_micro(battery.status().and_then(|status| status.voltage)))
+                as ShowFn,
+        ),
+        (
+            "voltage_min_design",
+            Box::new(move || format_micro(battery.info().and_then(|info| info.design_voltage)))
+                as ShowFn,
+        ),
+        (
+            "cycle_count",
+            Box::new(move || format_or_empty(battery.info().and_then(|info| info.cycle_count)))
+                as ShowFn,
+        ),
+        (
+            "technology",
+            Box::new(move || {
+                let technology = battery.info().map(|info| info.technology);
+                format_or_empty(technology)
+            }) as ShowFn,
+        ),
+        (
+            "model_name",
+            Box::new(move || format_or_empty(battery.info().map(|info| info.model))) as ShowFn,
+        ),
+        (
+            "serial_number",
+            Box::new(move || format_or_empty(battery.info().map(|info| info.serial_number)))
+                as ShowFn,
+        ),
+        (
+            "manufacturer",
+            Box::new(move || format_or_empty(battery.info().map(|info| info.manufacturer)))
+                as ShowFn,
+        ),
+    ];
+
+    SysDevice {
+        class: "power_supply",
+        name: battery.name().to_string(),
+        dev: None,
+        uevent_vars: vec![("POWER_SUPPLY_NAME", battery.name().to_string())],
+        attrs,
+    }
+}
+
+/// Formats the value in µWh, µW, µAh, µA, or µV, which is in mWh, mW, mAh, mA, or mV.
+fn format_micro(value: Option<u32>) -> String {
+    format_or_empty(value.map(|value| value as u64 * 1000))
+}
+
+fn battery_status_of(battery: &Battery) -> &'static str {
+    let Some(status) = battery.status() else {
+        return "Unknown";
+    };
+
+    match status.state {
+        ChargingState::Charging => "Charging",
+        ChargingState::Discharging => "Discharging",
+        ChargingState::Idle => {
+            let is_full = battery
+                .info()
+                .and_then(|info| info.last_full_capacity)
+                .zip(status.remaining_capacity)
+                .is_some_and(|(full, remaining)| remaining >= full);
+            if is_full { "Full" } else { "Not charging" }
+        }
+    }
+}
@@ -0,0 +1,655 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use ostd::mm::VmIo;
+
+use super::{
+    DrmDevice,
+    gem::DumbBuffer,
+    kms::{
+        DRM_FORMAT_ARGB8888, DRM_FORMAT_XRGB8888, DRM_MODE_FB_INTERLACED, DRM_MODE_PAGE_FLIP_EVENT,
+        DrmModeCrtcPageFlip, DrmModeFbCmd2,
+    },
+};
+use crate::{
+    current_userspace,
+    events::IoEvents,
+    fs::{
+        file::{FileIo, Mappable, StatusFlags},
+        vfs::inode::InodeIo,
+    },
+    prelude::*,
+    process::{
+        credentials::capabilities::CapSet,
+        posix_thread::AsPosixThread,
+        signal::{PollHandle, Pollable, Pollee},
+    },
+    time::clocks::MonotonicClock,
+    util::ioctl::{RawIoctl, dispatch_ioctl},
+};
+
+/// The maximum number of bytes of the pending events of a file.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/gpu/drm/drm_file.c>.
+const EVENT_SPACE: usize = 4096;
+
+/// The ID of the next opened file.
+static NEXT_FILE_ID: AtomicU64 = AtomicU64::new(0);
+
+/// `struct drm_version` in Linux.
+#[repr(C)]
+#[padding_struct]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct DrmVersion {
+    version_major: i32,
+    version_minor: i32,
+    version_patchlevel: i32,
+    name_len: u64,
+    name: u64,
+    date_len: u64,
+    date: u64,
+    desc_len: u64,
+    desc: u64,
+}
+
+/// `struct drm_gem_close` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct DrmGemClose {
+    handle: u32,
+    pad: u32,
+}
+
+/// `struct drm_get_cap` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct DrmGetCap {
+    capability: u64,
+    value: u64,
+}
+
+/// `struct drm_set_client_cap` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct DrmSetClientCap {
+    capability: u64,
+    value: u64,
+}
+
+/// `struct drm_mode_create_dumb` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct DrmModeCreateDumb {
+    height: u32,
+    width: u32,
+    bpp: u32,
+    flags: u32,
+    handle: u32,
+    pitch: u32,
+    size: u64,
+}
+
+/// `struct drm_mode_map_dumb` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct DrmModeMapDumb {
+    handle: u32,
+    pad: u32,
+    offset: u64,
+}
+
+/// `struct drm_mode_destroy_dumb` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct DrmModeDestroyDumb {
+    handle: u32,
+}
+
+/// `struct drm_event_vblank` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct DrmEventVblank {
+    type_: u32,
+    length: u32,
+    user_data: u64,
+    tv_sec: u32,
+    tv_usec: u32,
+    sequence: u32,
+    crtc_id: u32,
+}
+
+const DRM_EVENT_FLIP_COMPLETE: u32 = 0x02;
+
+// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/drm/drm.h>
+
+const DRM_CAP_DUMB_BUFFER: u64 = 0x1;
+const DRM_CAP_VBLANK_HIGH_CRTC: u64 = 0x2;
+const DRM_CAP_DUMB_PREFERRED_DEPTH: u64 = 0x3;
+const DRM_CAP_DUMB_PREFER_SHADOW: u64 = 0x4;
+const DRM_CAP_PRIME: u64 = 0x5;
+const DRM_CAP_TIMESTAMP_MONOTONIC: u64 = 0x6;
+const DRM_CAP_ASYNC_PAGE_FLIP: u64 = 0x7;
+const DRM_CAP_CURSOR_WIDTH: u64 = 0x8;
+const DRM_CAP_CURSOR_HEIGHT: u64 = 0x9;
+const DRM_CAP_ADDFB2_MODIFIERS: u64 = 0x10;
+const DRM_CAP_PAGE_FLIP_TARGET: u64 = 0x11;
+const DRM_CAP_CRTC_IN_VBLANK_EVENT: u64 = 0x12;
+const DRM_CAP_SYNCOBJ: u64 = 0x13;
+const DRM_CAP_SYNCOBJ_TIMELINE: u64 = 0x14;
+const DRM_CAP_ATOMIC_ASYNC_PAGE_FLIP: u64 = 0x15;
+
+const DRM_CLIENT_CAP_STEREO_3D: u64 = 1;
+const DRM_CLIENT_CAP_UNIVERSAL_PLANES: u64 = 2;
+const DRM_CLIENT_CAP_ATOMIC: u64 = 3;
+const DRM_CLIENT_CAP_ASPECT_RATIO: u64 = 4;
+const DRM_CLIENT_CAP_WRITEBACK_CONNECTORS: u64 = 5;
+const DRM_CLIENT_CAP_CURSOR_PLANE_HOTSPOT: u64 = 6;
+
+pub(super) struct DrmFile {
+    device: Arc<DrmDevice>,
+    /// The unique ID of the file
+    id: u64,
+    handles: Mutex<BTreeMap<u32, Arc<DumbBuffer>>>,
+    events: Mutex<VecDeque<DrmEventVblank>>,
+    pollee: Pollee,
+    /// Whether the file has set `DRM_CLIENT_CAP_UNIVERSAL_PLANES`
+    is_universal_planes: AtomicBool,
+    /// Whether the file has ever been the master
+    was_master: AtomicBool,
+}
+
+impl DrmFile {
+    pub(super) fn new(device: Arc<DrmDevice>) -> Self {
+        let id = NEXT_FILE_ID.fetch_add(1, Ordering::Relaxed);
+
+        // Like Linux, the first file becomes the master if there is no master.
+        let mut master = device.master.lock();
+        let is_master = master.is_none();
+        if is_master {
+            *master = Some(id);
+        }
+        drop(master);
+
+        Self {
+            device,
+            id,
+            handles: Mutex::new(BTreeMap::new()),
+            events: Mutex::new(VecDeque::new()),
+            pollee: Pollee::new(),
+            is_universal_planes: AtomicBool::new(false),
+            was_master: AtomicBool::new(is_master),
+        }
+    }
+
+    fn is_master(&self) -> bool {
+        *self.device.master.lock() == Some(self.id)
+    }
+
+    fn check_master(&self) -> Result<()> {
+        if !self.is_master() {
+            return_errno_with_message!(Errno::EACCES, "the file is not the DRM master");
+        }
+
+        Ok(())
+    }
+
+    /// Checks whether the file is allowed to become the master or drop the master.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/gpu/drm/drm_auth.c>.
+    fn check_master_perm(&self) -> Result<()> {
+        if self.was_master.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+        if !credentials.effective_capset().contains(CapSet::SYS_ADMIN) {
+            return_errno_with_message!(Errno::EACCES, "the file is not allowed to be the master");
+        }
+
+        Ok(())
+    }
+
+    fn set_master(&self) -> Result<()> {
+        let mut master = self.device.master.lock();
+
+        self.check_master_perm()?;
+        if *master == Some(self.id) {
+            return Ok(());
+        }
+        if master.is_some() {
+            return_errno_with_message!(Errno::EBUSY, "another file is the DRM master");
+        }
+
+        *master = Some(self.id);
+        self.was_master.store(true, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    fn drop_master(&self) -> Result<()> {
+        let mut master = self.device.master.lock();
+
+        self.check_master_perm()?;
+        if *master != Some(self.id) {
+            return_errno_with_message!(Errno::EINVAL, "the file is not the DRM master");
+        }
+
+        *master = None;
+
+        Ok(())
+    }
+
+    fn handle_get_version(&self, version: &mut DrmVersion) -> Result<()> {
+        version.version_major = 0;
+        version.version_minor = 1;
+        version.version_patchlevel = 0;
+
+        copy_field(&mut version.name_len, version.name, "virtio_gpu")?;
+        copy_field(&mut version.date_len, version.date, "0")?;
+        copy_field(&mut version.desc_len, version.desc, "virtio GPU")?;
+
+        Ok(())
+    }
+
+    fn handle_set_client_cap(&self, client_cap: &DrmSetClientCap) -> Result<()> {
+        match client_cap.capability {
+            DRM_CLIENT_CAP_STEREO_3D | DRM_CLIENT_CAP_ASPECT_RATIO => {
+                if client_cap.value > 1 {
+                    return_errno_with_message!(Errno::EINVAL, "the capability value is invalid");
+                }
+                // The stereo modes and the aspect ratios are never reported, so the capabilities
+                // change nothing.
+            }
+            DRM_CLIENT_CAP_UNIVERSAL_PLANES => {
+                if client_cap.value > 1 {
+                    return_errno_with_message!(Errno::EINVAL, "the capability value is invalid");
+                }
+                self.is_universal_planes
+                    .store(client_cap.value == 1, Ordering::Relaxed);
+            }
+            DRM_CLIENT_CAP_ATOMIC | DRM_CLIENT_CAP_CURSOR_PLANE_HOTSPOT => {
+                return_errno_with_message!(
+                    Errno::EOPNOTSUPP,
+                    "the client capability is not supported"
+                );
+            }
+            DRM_CLIENT_CAP_WRITEBACK_CONNECTORS => {
+                // Linux requires `DRM_CLIENT_CAP_ATOMIC` to be set first.
+                return_errno_with_message!(Errno::EINVAL, "the atomic capability is not set");
+            }
+            _ => {
+                return_errno_with_message!(Errno::EINVAL, "the client capability is unknown");
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_create_dumb(&self, create: &mut DrmModeCreateDumb) -> Result<()> {
+        if create.bpp != 32 {
+            return_errno_with_message!(Errno::EINVAL, "only 32 bits per pixel are supported");
+        }
+        if create.width == 0 || create.height == 0 || create.flags != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the dumb buffer arguments are invalid");
+        }
+
+        let pitch = create
+            .width
+            .checked_mul(4)
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "the width is too large"))?;
+        let size = (pitch as usize)
+            .checked_mul(create.height as usize)
+            .and_then(|size| size.checked_next_multiple_of(PAGE_SIZE))
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "the size is too large"))?;
+
+        let buffer = self.device.gem.alloc_buffer(size)?;
+        create.handle = self.add_handle(buffer);
+        create.pitch = pitch;
+        create.size = size as u64;
+
+        Ok(())
+    }
+
+    fn add_handle(&self, buffer: Arc<DumbBuffer>) -> u32 {
+        let mut handles = self.handles.lock();
+        // Like Linux, the smallest unused handle is allocated, starting from one.
+        let handle = (1..=u32::MAX)
+            .find(|handle| !handles.contains_key(handle))
+            .unwrap();
+        handles.insert(handle, buffer);
+        handle
+    }
+
+    fn lookup_handle(&self, handle: u32) -> Result<Arc<DumbBuffer>> {
+        self.handles
+            .lock()
+            .get(&handle)
+            .cloned()
+            .ok_or_else(|| Error::with_message(Errno::ENOENT, "the handle does not exist"))
+    }
+
+    fn remove_handle(&self, handle: u32) -> Result<()> {
+        if self.handles.lock().remove(&handle).is_none() {
+            return_errno_with_message!(Errno::EINVAL, "the handle does not exist");
+        }
+
+        Ok(())
+    }
+
+    fn handle_add_fb(&self, fb_cmd: &mut DrmModeFbCmd2) -> Result<()> {
+        if fb_cmd.flags & !DRM_MODE_FB_INTERLACED != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the framebuffer flags are not supported");
+        }
+        // Only single-plane formats are supported.
+        for i in 1..4 {
+            if fb_cmd.handles[i] != 0 || fb_cmd.pitches[i] != 0 || fb_cmd.offsets[i] != 0 {
+                return_errno_with_message!(Errno::EINVAL, "the extra planes are not supported");
+            }
+        }
+
+        let buffer = self.lookup_handle(fb_cmd.handles[0])?;
+        fb_cmd.fb_id = self.device.kms.lock().add_fb(
+            self.id,
+            buffer,
+            fb_cmd.width,
+            fb_cmd.height,
+            fb_cmd.pitches[0],
+            fb_cmd.offsets[0],
+            fb_cmd.pixel_format,
+        )?;
+
+        Ok(())
+    }
+
+    fn handle_page_flip(&self, flip: &DrmModeCrtcPageFlip) -> Result<()> {
+        self.check_master()?;
+
+        let is_event_requested = flip.flags & DRM_MODE_PAGE_FLIP_EVENT != 0;
+        let mut events = self.events.lock();
+        if is_event_requested && (events.len() + 1) * size_of::<DrmEventVblank>() > EVENT_SPACE {
+            return_errno_with_message!(Errno::ENOMEM, "there is no space for the event");
+        }
+
+        let info = self.device.kms.lock().page_flip(flip)?;
+        if !is_event_requested {
+            return Ok(());
+        }
+
+        let now = MonotonicClock::get().read_time();
+        events.push_back(DrmEventVblank {
+            type_: DRM_EVENT_FLIP_COMPLETE,
+            length: size_of::<DrmEventVblank>() as u32,
+            user_data: flip.user_data,
+            tv_sec: now.as_secs() as u32,
+            tv_usec: now.subsec_micros(),
+            sequence: info.sequence,
+            crtc_id: info.crtc_id,
+        });
+        drop(events);
+
+        self.pollee.notify(IoEvents::IN);
+
+        Ok(())
+    }
+
+    fn try_read(&self, writer: &mut VmWriter) -> Result<usize> {
+        const EVENT_SIZE: usize = size_of::<DrmEventVblank>();
+
+        let mut events = self.events.lock();
+        if events.is_empty() {
+            return_errno_with_message!(Errno::EAGAIN, "the DRM file has no events");
+        }
+
+        // Like Linux, only complete events are read, so zero is returned if the buffer cannot hold
+        // the first event.
+        let mut len = 0;
+        while let Some(event) = events.front() {
+            if writer.avail() < EVENT_SIZE {
+                break;
+            }
+            writer.write_val(event)?;
+            events.pop_front();
+            len += EVENT_SIZE;
+        }
+        drop(events);
+
+        self.pollee.invalidate();
+
+        Ok(len)
+    }
+
+    fn check_io_events(&self) -> IoEvents {
+        if self.events.lock().is_empty() {
+            IoEvents::empty()
+        } else {
+            IoEvents::IN
+        }
+    }
+}
+
+/// Copies a string to the user buffer and sets its length, as `drm_copy_field` in Linux does.
+fn copy_field(buf_len: &mut u64, buf: u64, value: &str) -> Result<()> {
+    let len = (*buf_len as usize).min(value.len());
+    *buf_len = value.len() as u64;
+    if len > 0 && buf != 0 {
+        current_userspace!().write_bytes(buf as Vaddr, &value.as_bytes()[..len])?;
+    }
+
+    Ok(())
+}
+
+fn get_cap(capability: u64) -> Result<u64> {
+    let value = match capability {
+        DRM_CAP_DUMB_BUFFER => 1,
+        DRM_CAP_VBLANK_HIGH_CRTC => 1,
+        DRM_CAP_DUMB_PREFERRED_DEPTH => 24,
+        DRM_CAP_DUMB_PREFER_SHADOW => 0,
+        DRM_CAP_PRIME => 0,
+        DRM_CAP_TIMESTAMP_MONOTONIC => 1,
+        DRM_CAP_ASYNC_PAGE_FLIP => 0,
+        DRM_CAP_CURSOR_WIDTH | DRM_CAP_CURSOR_HEIGHT => 64,
+        DRM_CAP_ADDFB2_MODIFIERS => 0,
+        DRM_CAP_PAGE_FLIP_TARGET => 0,
+        DRM_CAP_CRTC_IN_VBLANK_EVENT => 1,
+        DRM_CAP_SYNCOBJ | DRM_CAP_SYNCOBJ_TIMELINE | DRM_CAP_ATOMIC_ASYNC_PAGE_FLIP => 0,
+        _ => return_errno_with_message!(Errno::EINVAL, "the capability is unknown"),
+    };
+
+    Ok(value)
+}
+
+impl Pollable for DrmFile {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.pollee
+            .poll_with(mask, poller, || self.check_io_events())
+    }
+}
+
+impl InodeIo for DrmFile {
+    fn read_at(
+        &self,
+        _offset: usize,
+        writer: &mut VmWriter,
+        status_flags: StatusFlags,
+    ) -> Result<usize> {
+        if status_flags.contains(StatusFlags::O_NONBLOCK) {
+            self.try_read(writer)
+        } else {
+            self.wait_events(IoEvents::IN, None, || self.try_read(writer))
+        }
+    }
+
+    fn write_at(
+        &self,
+        _offset: usize,
+        _reader: &mut VmReader,
+        _status_flags: StatusFlags,
+    ) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "DRM files cannot be written");
+    }
+}
+
+impl FileIo for DrmFile {
+    fn check_seekable(&self) -> Result<()> {
+        return_errno_with_message!(Errno::ESPIPE, "the inode is a DRM file");
+    }
+
+    fn is_offset_aware(&self) -> bool {
+        false
+    }
+
+    fn mappable(&self) -> Result<Mappable> {
+        // The offsets of the dumb buffers in the VMO are the offsets for `mmap`.
+        Ok(Mappable::Vmo(self.device.gem.vmo().clone()))
+    }
+
+    fn ioctl(&self, raw_ioctl: RawIoctl) -> Result<i32> {
+        use super::ioctl_defs::*;
+
+        dispatch_ioctl!(match raw_ioctl {
+            cmd @ GetVersion => {
+                let mut version = cmd.read()?;
+                self.handle_get_version(&mut version)?;
+                cmd.write(&version)?;
+            }
+            cmd @ GemClose => {
+                let close = cmd.read()?;
+                self.remove_handle(close.handle)?;
+            }
+            cmd @ GetCap => {
+                let mut cap = cmd.read()?;
+                cap.value = get_cap(cap.capability)?;
+                cmd.write(&cap)?;
+            }
+            cmd @ SetClientCap => {
+                self.handle_set_client_cap(&cmd.read()?)?;
+            }
+            SetMaster => {
+                self.set_master()?;
+            }
+            DropMaster => {
+                self.drop_master()?;
+            }
+            cmd @ GetResources => {
+                let mut res = cmd.read()?;
+                self.device.kms.lock().get_resources(self.id, &mut res)?;
+                cmd.write(&res)?;
+            }
+            cmd @ GetCrtc => {
+                let mut crtc = cmd.read()?;
+                self.device.kms.lock().get_crtc(&mut crtc)?;
+                cmd.write(&crtc)?;
+            }
+            cmd @ SetCrtc => {
+                self.check_master()?;
+                self.device.kms.lock().set_crtc(&cmd.read()?)?;
+            }
+            cmd @ GetEncoder => {
+                let mut encoder = cmd.read()?;
+                self.device.kms.lock().get_encoder(&mut encoder)?;
+                cmd.write(&encoder)?;
+            }
+            cmd @ GetConnector => {
+                let mut connector = cmd.read()?;
+                self.device.kms.lock().get_connector(&mut connector)?;
+                cmd.write(&connector)?;
+            }
+            cmd @ GetFb => {
+                let mut fb_cmd = cmd.read()?;
+                self.device.kms.lock().get_fb(&mut fb_cmd)?;
+                cmd.write(&fb_cmd)?;
+            }
+            cmd @ AddFb => {
+                let mut fb_cmd = cmd.read()?;
+                let pixel_format = match (fb_cmd.bpp, fb_cmd.depth) {
+                    (32, 24) => DRM_FORMAT_XRGB8888,
+                    (32, 32) => DRM_FORMAT_ARGB8888,
+                    _ => return_errno_with_message!(Errno::EINVAL, "the depth is not supported"),
+                };
+
+                let mut fb_cmd2 = DrmModeFbCmd2::new_zeroed();
+                fb_cmd2.width = fb_cmd.width;
+                fb_cmd2.height = fb_cmd.height;
+                fb_cmd2.pixel_format = pixel_format;
+                fb_cmd2.handles[0] = fb_cmd.handle;
+                fb_cmd2.pitches[0] = fb_cmd.pitch;
+                self.handle_add_fb(&mut fb_cmd2)?;
+
+                fb_cmd.fb_id = fb_cmd2.fb_id;
+                cmd.write(&fb_cmd)?;
+            }
+            cmd @ AddFb2 => {
+                let mut fb_cmd = cmd.read()?;
+                self.handle_add_fb(&mut fb_cmd)?;
+                cmd.write(&fb_cmd)?;
+            }
+            cmd @ RemoveFb => {
+                let fb_id = cmd.read()?;
+                self.device.kms.lock().remove_fb(self.id, fb_id)?;
+            }
+            cmd @ PageFlip => {
+                self.handle_page_flip(&cmd.read()?)?;
+            }
+            cmd @ DirtyFb => {
+                self.check_master()?;
+                self.device.kms.lock().dirty_fb(&cmd.read()?)?;
+            }
+            cmd @ CreateDumb => {
+                let mut create = cmd.read()?;
+                self.handle_create_dumb(&mut create)?;
+                cmd.write(&create)?;
+            }
+            cmd @ MapDumb => {
+                let mut map = cmd.read()?;
+                map.offset = self.lookup_handle(map.handle)?.offset() as u64;
+                cmd.write(&map)?;
+            }
+            cmd @ DestroyDumb => {
+                let destroy = cmd.read()?;
+                self.remove_handle(destroy.handle)?;
+            }
+            cmd @ GetPlaneResources => {
+                let mut res = cmd.read()?;
+                let is_universal_planes = self.is_universal_planes.load(Ordering::Relaxed);
+                self.device
+                    .kms
+                    .lock()
+                    .get_plane_resources(is_universal_planes, &mut res)?;
+                cmd.write(&res)?;
+            }
+            cmd @ GetPlane => {
+                let mut plane = cmd.read()?;
+                self.device.kms.lock().get_plane(&mut plane)?;
+                cmd.write(&plane)?;
+            }
+            cmd @ GetObjectProperties => {
+                let mut props = cmd.read()?;
+                self.device.kms.lock().get_object_properties(&mut props)?;
+                cmd.write(&props)?;
+            }
+            _ => {
+                // Like evdev files, DRM files return `EINVAL` for unknown ioctl commands.
+                // Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/gpu/drm/drm_ioctl.c>
+                return_errno_with_message!(
+                    Errno::EINVAL,
+                    "the ioctl command is not supported by DRM files"
+                );
+            }
+        });
+
+        Ok(0)
+    }
+}
+
+impl Drop for DrmFile {
+    fn drop(&mut self) {
+        let mut master = self.device.master.lock();
+        if *master == Some(self.id) {
+            *master = None;
+        }
+        drop(master);
+
+        self.device.kms.lock().remove_fbs_of(self.id);
+    }
+}
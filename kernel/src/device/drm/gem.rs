@@ -0,0 +1,168 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Dumb buffers.
+//!
+//! All the dumb buffers of a DRM device live in a single VMO, which is the object that is
+//! mapped when the device is `mmap`ed. Each buffer occupies a distinct range of the VMO, whose
+//! offset is the "fake" offset that the user passes to `mmap`.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use align_ext::AlignExt;
+use ostd::mm::{FrameAllocOptions, UFrame};
+
+use crate::{
+    prelude::*,
+    vm::vmo::{Pager, Vmo, VmoOptions},
+};
+
+/// The size of the VMO that holds the dumb buffers.
+///
+/// The VMO is backed by memory only where the buffers are, so the size only limits the total size
+/// of the buffers that are ever created.
+const GEM_SPACE_SIZE: usize = 1 << 40;
+
+/// The space where the dumb buffers of a DRM device live.
+pub(super) struct GemSpace {
+    vmo: Arc<Vmo>,
+    pager: Arc<GemPager>,
+    /// The offset of the next buffer.
+    ///
+    /// The offsets are never reused, since a destroyed buffer may still be mapped.
+    next_offset: AtomicUsize,
+}
+
+impl GemSpace {
+    pub(super) fn new() -> Result<Arc<Self>> {
+        let pager = Arc::new(GemPager {
+            buffers: Mutex::new(BTreeMap::new()),
+        });
+        let vmo = VmoOptions::new(GEM_SPACE_SIZE)
+            .pager(pager.clone())
+            .alloc()?;
+
+        Ok(Arc::new(Self {
+            vmo,
+            pager,
+            next_offset: AtomicUsize::new(0),
+        }))
+    }
+
+    /// Returns the VMO that holds the buffers.
+    pub(super) fn vmo(&self) -> &Arc<Vmo> {
+        &self.vmo
+    }
+
+    /// Allocates a buffer.
+    pub(super) fn alloc_buffer(self: &Arc<Self>, size: usize) -> Result<Arc<DumbBuffer>> {
+        let size = size.align_up(PAGE_SIZE);
+
+        let offset = self
+            .next_offset
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |offset| {
+                offset
+                    .checked_add(size)
+                    .filter(|end| *end <= GEM_SPACE_SIZE)
+            })
+            .map_err(|_| Error::with_message(Errno::ENOMEM, "the GEM space is exhausted"))?;
+
+        self.pager
+            .buffers
+            .lock()
+            .insert(offset / PAGE_SIZE, size / PAGE_SIZE);
+
+        Ok(Arc::new(DumbBuffer {
+            space: self.clone(),
+            offset,
+            size,
+        }))
+    }
+}
+
+/// A dumb buffer, i.e., a buffer in the memory that can be scanned out.
+pub(super) struct DumbBuffer {
+    space: Arc<GemSpace>,
+    offset: usize,
+    size: usize,
+}
+
+impl DumbBuffer {
+    /// Returns the offset of the buffer in the VMO, which is also the offset for `mmap`.
+    pub(super) fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the size of the buffer.
+    pub(super) fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Reads the buffer content starting from the offset in the buffer.
+    pub(super) fn read(&self, offset: usize, writer: &mut VmWriter) -> Result<()> {
+        if offset.saturating_add(writer.avail()) > self.size {
+            return_errno_with_message!(Errno::EINVAL, "the range exceeds the buffer");
+        }
+
+        self.space.vmo.read(self.offset + offset, writer)
+    }
+}
+
+impl Drop for DumbBuffer {
+    fn drop(&mut self) {
+        self.space
+            .pager
+            .buffers
+            .lock()
+            .remove(&(self.offset / PAGE_SIZE));
+
+        // The pages that are still mapped are kept alive by the mappings.
+        let _ = self
+            .space
+            .vmo
+            .decommit(self.offset..self.offset + self.size);
+    }
+}
+
+/// The pager that provides pages only for the existing buffers.
+///
+/// Accessing other ranges of the VMO fails, so that the user cannot consume memory outside the
+/// buffers.
+struct GemPager {
+    /// The existing buffers, which are the maps from the first page indexes to the numbers of
+    /// pages.
+    buffers: Mutex<BTreeMap<usize, usize>>,
+}
+
+impl GemPager {
+    fn check_page(&self, idx: usize) -> Result<()> {
+        let buffers = self.buffers.lock();
+        let is_valid = buffers
+            .range(..=idx)
+            .next_back()
+            .is_some_and(|(start, nr_pages)| idx < start + nr_pages);
+        if !is_valid {
+            return_errno_with_message!(Errno::EFAULT, "the page is not in any dumb buffer");
+        }
+
+        Ok(())
+    }
+}
+
+impl Pager for GemPager {
+    fn commit_page(&self, idx: usize) -> Result<UFrame> {
+        self.check_page(idx)?;
+        Ok(FrameAllocOptions::new().alloc_frame()?.into())
+    }
+
+    fn update_page(&self, _idx: usize) -> Result<()> {
+        Ok(())
+    }
+
+    fn decommit_page(&self, _idx: usize) -> Result<()> {
+        Ok(())
+    }
+
+    fn commit_overwrite(&self, idx: usize) -> Result<UFrame> {
+        self.commit_page(idx)
+    }
+}
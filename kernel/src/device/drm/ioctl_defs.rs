@@ -0,0 +1,65 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::{
+    file::{
+        DrmGemClose, DrmGetCap, DrmModeCreateDumb, DrmModeDestroyDumb, DrmModeMapDumb,
+        DrmSetClientCap, DrmVersion,
+    },
+    kms::{
+        DrmModeCardRes, DrmModeCrtc, DrmModeCrtcPageFlip, DrmModeFbCmd, DrmModeFbCmd2,
+        DrmModeFbDirtyCmd, DrmModeGetConnector, DrmModeGetEncoder, DrmModeGetPlane,
+        DrmModeGetPlaneRes, DrmModeObjGetProperties,
+    },
+};
+use crate::util::ioctl::{InData, InOutData, NoData, ioc};
+
+// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/drm/drm.h>
+
+pub(super) type GetVersion =
+    ioc!(DRM_IOCTL_VERSION,                b'd', 0x00, InOutData<DrmVersion>);
+pub(super) type GemClose =
+    ioc!(DRM_IOCTL_GEM_CLOSE,              b'd', 0x09, InData<DrmGemClose>);
+pub(super) type GetCap =
+    ioc!(DRM_IOCTL_GET_CAP,                b'd', 0x0c, InOutData<DrmGetCap>);
+pub(super) type SetClientCap =
+    ioc!(DRM_IOCTL_SET_CLIENT_CAP,         b'd', 0x0d, InData<DrmSetClientCap>);
+
+pub(super) type SetMaster =
+    ioc!(DRM_IOCTL_SET_MASTER,             b'd', 0x1e, NoData);
+pub(super) type DropMaster =
+    ioc!(DRM_IOCTL_DROP_MASTER,            b'd', 0x1f, NoData);
+
+pub(super) type GetResources =
+    ioc!(DRM_IOCTL_MODE_GETRESOURCES,      b'd', 0xA0, InOutData<DrmModeCardRes>);
+pub(super) type GetCrtc =
+    ioc!(DRM_IOCTL_MODE_GETCRTC,           b'd', 0xA1, InOutData<DrmModeCrtc>);
+pub(super) type SetCrtc =
+    ioc!(DRM_IOCTL_MODE_SETCRTC,           b'd', 0xA2, InOutData<DrmModeCrtc>);
+pub(super) type GetEncoder =
+    ioc!(DRM_IOCTL_MODE_GETENCODER,        b'd', 0xA6, InOutData<DrmModeGetEncoder>);
+pub(super) type GetConnector =
+    ioc!(DRM_IOCTL_MODE_GETCONNECTOR,      b'd', 0xA7, InOutData<DrmModeGetConnector>);
+pub(super) type GetFb =
+    ioc!(DRM_IOCTL_MODE_GETFB,             b'd', 0xAD, InOutData<DrmModeFbCmd>);
+pub(super) type AddFb =
+    ioc!(DRM_IOCTL_MODE_ADDFB,             b'd', 0xAE, InOutData<DrmModeFbCmd>);
+pub(super) type RemoveFb =
+    ioc!(DRM_IOCTL_MODE_RMFB,              b'd', 0xAF, InOutData<u32>);
+pub(super) type PageFlip =
+    ioc!(DRM_IOCTL_MODE_PAGE_FLIP,         b'd', 0xB0, InOutData<DrmModeCrtcPageFlip>);
+pub(super) type DirtyFb =
+    ioc!(DRM_IOCTL_MODE_DIRTYFB,           b'd', 0xB1, InOutData<DrmModeFbDirtyCmd>);
+pub(super) type CreateDumb =
+    ioc!(DRM_IOCTL_MODE_CREATE_DUMB,       b'd', 0xB2, InOutData<DrmModeCreateDumb>);
+pub(super) type MapDumb =
+    ioc!(DRM_IOCTL_MODE_MAP_DUMB,          b'd', 0xB3, InOutData<DrmModeMapDumb>);
+pub(super) type DestroyDumb =
+    ioc!(DRM_IOCTL_MODE_DESTROY_DUMB,      b'd', 0xB4, InOutData<DrmModeDestroyDumb>);
+pub(super) type GetPlaneResources =
+    ioc!(DRM_IOCTL_MODE_GETPLANERESOURCES, b'd', 0xB5, InOutData<DrmModeGetPlaneRes>);
+pub(super) type GetPlane =
+    ioc!(DRM_IOCTL_MODE_GETPLANE,          b'd', 0xB6, InOutData<DrmModeGetPlane>);
+pub(super) type AddFb2 =
+    ioc!(DRM_IOCTL_MODE_ADDFB2,            b'd', 0xB8, InOutData<DrmModeFbCmd2>);
+pub(super) type GetObjectProperties =
+    ioc!(DRM_IOCTL_MODE_OBJ_GETPROPERTIES, b'd', 0xB9, InOutData<DrmModeObjGetProperties>);
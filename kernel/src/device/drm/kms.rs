@@ -0,0 +1,1103 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Kernel mode setting (KMS).
+//!
+//! Each scanout of the GPU is exposed as a pipeline of a primary plane, a CRTC, an encoder, and a
+//! connector, as in Linux.
+//!
+//! The framebuffers are not scanned out directly. Instead, each enabled CRTC owns a resource on
+//! the host, and the content of its framebuffer is copied to the resource when the framebuffer is
+//! displayed (via `DRM_IOCTL_MODE_SETCRTC` or `DRM_IOCTL_MODE_PAGE_FLIP`) or marked dirty (via
+//! `DRM_IOCTL_MODE_DIRTYFB`).
+//!
+//! Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/gpu/drm/drm_crtc.c>.
+
+use alloc::format;
+
+use aster_virtio::device::gpu::{Rect, ResourceFormat, Scanout, device::GpuDevice};
+use ostd::mm::{HasDaddr, HasSize, VmIo, dma::DmaStream, io::util::HasVmReaderWriter};
+
+use super::gem::DumbBuffer;
+use crate::{current_userspace, prelude::*};
+
+/// The minimum and maximum sizes of the modes and the framebuffers.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/gpu/drm/virtio/virtgpu_drv.h>.
+const MIN_SIZE: u32 = 32;
+const MAX_SIZE: u32 = 8192;
+
+/// The size of the preferred mode if the host does not give one.
+const DEFAULT_SIZE: (u32, u32) = (1024, 768);
+
+/// The sizes of the modes that are available besides the preferred one.
+const STANDARD_SIZES: [(u32, u32); 7] = [
+    (1920, 1200),
+    (1920, 1080),
+    (1600, 1200),
+    (1280, 1024),
+    (1280, 720),
+    (1024, 768),
+    (800, 600),
+];
+
+/// The refresh rate of the modes.
+const REFRESH_RATE: u32 = 60;
+
+/// The number of bytes per pixel of the supported formats.
+const BYTES_PER_PIXEL: u32 = 4;
+
+// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/drm/drm_fourcc.h>
+pub(super) const DRM_FORMAT_XRGB8888: u32 = u32::from_le_bytes(*b"XR24");
+pub(super) const DRM_FORMAT_ARGB8888: u32 = u32::from_le_bytes(*b"AR24");
+
+/// The formats that are supported by the primary planes.
+const SUPPORTED_FORMATS: [u32; 2] = [DRM_FORMAT_XRGB8888, DRM_FORMAT_ARGB8888];
+
+// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/drm/drm_mode.h>
+
+const DRM_MODE_TYPE_PREFERRED: u32 = 1 << 3;
+const DRM_MODE_TYPE_DRIVER: u32 = 1 << 6;
+
+const DRM_MODE_FLAG_PHSYNC: u32 = 1 << 0;
+const DRM_MODE_FLAG_NVSYNC: u32 = 1 << 3;
+
+const DRM_MODE_ENCODER_VIRTUAL: u32 = 5;
+const DRM_MODE_CONNECTOR_VIRTUAL: u32 = 15;
+
+const DRM_MODE_CONNECTED: u32 = 1;
+const DRM_MODE_DISCONNECTED: u32 = 2;
+
+const DRM_MODE_OBJECT_CRTC: u32 = 0xcccccccc;
+const DRM_MODE_OBJECT_CONNECTOR: u32 = 0xc0c0c0c0;
+const DRM_MODE_OBJECT_ENCODER: u32 = 0xe0e0e0e0;
+const DRM_MODE_OBJECT_FB: u32 = 0xfbfbfbfb;
+const DRM_MODE_OBJECT_PLANE: u32 = 0xeeeeeeee;
+const DRM_MODE_OBJECT_ANY: u32 = 0;
+
+pub(super) const DRM_MODE_FB_INTERLACED: u32 = 1 << 0;
+
+pub(super) const DRM_MODE_PAGE_FLIP_EVENT: u32 = 1 << 0;
+const DRM_MODE_PAGE_FLIP_ASYNC: u32 = 1 << 1;
+
+const DRM_MODE_FB_DIRTY_ANNOTATE_COPY: u32 = 1 << 0;
+const DRM_MODE_FB_DIRTY_ANNOTATE_FILL: u32 = 1 << 1;
+const DRM_MODE_FB_DIRTY_MAX_CLIPS: u32 = 256;
+
+/// `struct drm_mode_modeinfo` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct DrmModeModeInfo {
+    clock: u32,
+    hdisplay: u16,
+    hsync_start: u16,
+    hsync_end: u16,
+    htotal: u16,
+    hskew: u16,
+    vdisplay: u16,
+    vsync_start: u16,
+    vsync_end: u16,
+    vtotal: u16,
+    vscan: u16,
+    vrefresh: u32,
+    flags: u32,
+    type_: u32,
+    name: [u8; 32],
+}
+
+impl DrmModeModeInfo {
+    /// Creates a mode with the reduced blanking timings.
+    ///
+    /// The timings only matter to real monitors, but the users may calculate the refresh rate
+    /// from them.
+    fn new(width: u32, height: u32, is_preferred: bool) -> Self {
+        let htotal = width + 160;
+        let vtotal = height + 30;
+
+        let mut name = [0u8; 32];
+        let name_str = format!("{}x{}", width, height);
+        name[..name_str.len()].copy_from_slice(name_str.as_bytes());
+
+        let mut type_ = DRM_MODE_TYPE_DRIVER;
+        if is_preferred {
+            type_ |= DRM_MODE_TYPE_PREFERRED;
+        }
+
+        Self {
+            clock: (htotal as u64 * vtotal as u64 * REFRESH_RATE as u64 / 1000) as u32,
+            hdisplay: width as u16,
+            hsync_start: (width + 48) as u16,
+            hsync_end: (width + 80) as u16,
+            htotal: htotal as u16,
+            hskew: 0,
+            vdisplay: height as u16,
+            vsync_start: (height + 3) as u16,
+            vsync_end: (height + 9) as u16,
+            vtotal: vtotal as u16,
+            vscan: 0,
+            vrefresh: REFRESH_RATE,
+            flags: DRM_MODE_FLAG_PHSYNC | DRM_MODE_FLAG_NVSYNC,
+            type_,
+            name,
+        }
+    }
+
+    /// Checks whether the mode from the user is valid.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/gpu/drm/drm_modes.c>.
+    fn check(&self) -> Result<()> {
+        let is_valid = self.clock != 0
+            && self.hdisplay != 0
+            && self.hsync_start >= self.hdisplay
+            && self.hsync_end >= self.hsync_start
+            && self.htotal >= self.hsync_end
+            && self.vdisplay != 0
+            && self.vsync_start >= self.vdisplay
+            && self.vsync_end >= self.vsync_start
+            && self.vtotal >= self.vsync_end;
+        if !is_valid {
+            return_errno_with_message!(Errno::EINVAL, "the mode is invalid");
+        }
+
+        let size_range = MIN_SIZE..=MAX_SIZE;
+        if !size_range.contains(&(self.hdisplay as u32))
+            || !size_range.contains(&(self.vdisplay as u32))
+        {
+            return_errno_with_message!(Errno::EINVAL, "the mode size is not supported");
+        }
+
+        Ok(())
+    }
+
+    fn width(&self) -> u32 {
+        self.hdisplay as u32
+    }
+
+    fn height(&self) -> u32 {
+        self.vdisplay as u32
+    }
+}
+
+/// `struct drm_mode_card_res` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct DrmModeCardRes {
+    fb_id_ptr: u64,
+    crtc_id_ptr: u64,
+    connector_id_ptr: u64,
+    encoder_id_ptr: u64,
+    count_fbs: u32,
+    count_crtcs: u32,
+    count_connectors: u32,
+    count_encoders: u32,
+    min_width: u32,
+    max_width: u32,
+    min_height: u32,
+    max_height: u32,
+}
+
+/// `struct drm_mode_crtc` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct DrmModeCrtc {
+    set_connectors_ptr: u64,
+    count_connectors: u32,
+    crtc_id: u32,
+    fb_id: u32,
+    x: u32,
+    y: u32,
+    gamma_size: u32,
+    mode_valid: u32,
+    mode: DrmModeModeInfo,
+}
+
+/// `struct drm_mode_get_encoder` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct DrmModeGetEncoder {
+    encoder_id: u32,
+    encoder_type: u32,
+    crtc_id: u32,
+    possible_crtcs: u32,
+    possible_clones: u32,
+}
+
+/// `struct drm_mode_get_connector` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct DrmModeGetConnector {
+    encoders_ptr: u64,
+    modes_ptr: u64,
+    props_ptr: u64,
+    prop_values_ptr: u64,
+    count_modes: u32,
+    count_props: u32,
+    count_encoders: u32,
+    encoder_id: u32,
+    connector_id: u32,
+    connector_type: u32,
+    connector_type_id: u32,
+    connection: u32,
+    mm_width: u32,
+    mm_height: u32,
+    subpixel: u32,
+    pad: u32,
+}
+
+/// `struct drm_mode_get_plane_res` in Linux.
+#[repr(C)]
+#[padding_struct]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct DrmModeGetPlaneRes {
+    plane_id_ptr: u64,
+    count_planes: u32,
+}
+
+/// `struct drm_mode_get_plane` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct DrmModeGetPlane {
+    plane_id: u32,
+    crtc_id: u32,
+    fb_id: u32,
+    possible_crtcs: u32,
+    gamma_size: u32,
+    count_format_types: u32,
+    format_type_ptr: u64,
+}
+
+/// `struct drm_mode_fb_cmd` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct DrmModeFbCmd {
+    pub(super) fb_id: u32,
+    pub(super) width: u32,
+    pub(super) height: u32,
+    pub(super) pitch: u32,
+    pub(super) bpp: u32,
+    pub(super) depth: u32,
+    pub(super) handle: u32,
+}
+
+/// `struct drm_mode_fb_cmd2` in Linux.
+#[repr(C)]
+#[padding_struct]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct DrmModeFbCmd2 {
+    pub(super) fb_id: u32,
+    pub(super) width: u32,
+    pub(super) height: u32,
+    pub(super) pixel_format: u32,
+    pub(super) flags: u32,
+    pub(super) handles: [u32; 4],
+    pub(super) pitches: [u32; 4],
+    pub(super) offsets: [u32; 4],
+    pub(super) modifier: [u64; 4],
+}
+
+/// `struct drm_mode_crtc_page_flip` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct DrmModeCrtcPageFlip {
+    pub(super) crtc_id: u32,
+    pub(super) fb_id: u32,
+    pub(super) flags: u32,
+    pub(super) reserved: u32,
+    pub(super) user_data: u64,
+}
+
+/// `struct drm_mode_fb_dirty_cmd` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct DrmModeFbDirtyCmd {
+    fb_id: u32,
+    flags: u32,
+    color: u32,
+    num_clips: u32,
+    clips_ptr: u64,
+}
+
+/// `struct drm_clip_rect` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct DrmClipRect {
+    x1: u16,
+    y1: u16,
+    x2: u16,
+    y2: u16,
+}
+
+/// `struct drm_mode_obj_get_properties` in Linux.
+#[repr(C)]
+#[padding_struct]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct DrmModeObjGetProperties {
+    props_ptr: u64,
+    prop_values_ptr: u64,
+    count_props: u32,
+    obj_id: u32,
+    obj_type: u32,
+}
+
+/// A framebuffer, i.e., a dumb buffer with the information of its layout.
+struct Framebuffer {
+    id: u32,
+    /// The ID of the file that creates the framebuffer
+    owner: u64,
+    width: u32,
+    height: u32,
+    pitch: u32,
+    offset: u32,
+    format: u32,
+    buffer: Arc<DumbBuffer>,
+}
+
+impl Framebuffer {
+    /// Copies a region of the framebuffer to the backing storage of a resource.
+    ///
+    /// The region starts at (`x`, `y`) in the framebuffer. It is copied to the same offset in the
+    /// resource as `rect` is in the resource.
+    fn copy_to(&self, backing: &DmaStream, resource_width: u32, x: u32, y: u32, rect: Rect) {
+        let row_len = (rect.width * BYTES_PER_PIXEL) as usize;
+
+        for row in 0..rect.height {
+            let src_offset = self.offset as usize
+                + (y + row) as usize * self.pitch as usize
+                + (x * BYTES_PER_PIXEL) as usize;
+            let dst_offset =
+                ((rect.y + row) * resource_width + rect.x) as usize * BYTES_PER_PIXEL as usize;
+
+            let mut writer = backing.writer().unwrap();
+            writer.skip(dst_offset).limit(row_len);
+            // The layout of the framebuffer has been checked, so this should not fail.
+            if let Err(err) = self.buffer.read(src_offset, &mut writer.to_fallible()) {
+                warn!("failed to copy the framebuffer: {:?}", err);
+            }
+        }
+
+        let start = (rect.y * resource_width * BYTES_PER_PIXEL) as usize;
+        let end = ((rect.y + rect.height) * resource_width * BYTES_PER_PIXEL) as usize;
+        backing.sync_to_device(start..end).unwrap();
+    }
+
+    fn depth(&self) -> u32 {
+        if self.format == DRM_FORMAT_ARGB8888 {
+            32
+        } else {
+            24
+        }
+    }
+}
+
+/// A pipeline from a primary plane to a connector, which is backed by a scanout of the GPU.
+struct Output {
+    scanout_id: u32,
+    plane_id: u32,
+    crtc_id: u32,
+    encoder_id: u32,
+    connector_id: u32,
+    scanout: Scanout,
+    crtc: CrtcState,
+}
+
+#[derive(Default)]
+struct CrtcState {
+    fb: Option<Arc<Framebuffer>>,
+    mode: Option<DrmModeModeInfo>,
+    x: u32,
+    y: u32,
+    resource: Option<HostResource>,
+    /// The number of the displayed frames, which is reported in the page flip events
+    sequence: u32,
+}
+
+/// A resource on the host and its backing storage.
+struct HostResource {
+    id: u32,
+    width: u32,
+    height: u32,
+    backing: DmaStream,
+}
+
+/// The information of a completed page flip.
+pub(super) struct PageFlipInfo {
+    pub(super) crtc_id: u32,
+    pub(super) sequence: u32,
+}
+
+/// The KMS states of a GPU.
+pub(super) struct Kms {
+    gpu: Arc<GpuDevice>,
+    outputs: Vec<Output>,
+    fbs: BTreeMap<u32, Arc<Framebuffer>>,
+    next_object_id: u32,
+}
+
+impl Kms {
+    pub(super) fn new(gpu: Arc<GpuDevice>) -> Result<Self> {
+        let scanouts = gpu.display_info()?;
+
+        let mut kms = Self {
+            gpu,
+            outputs: Vec::with_capacity(scanouts.len()),
+            fbs: BTreeMap::new(),
+            next_object_id: 1,
+        };
+        for (scanout_id, scanout) in scanouts.into_iter().enumerate() {
+            let output = Output {
+                scanout_id: scanout_id as u32,
+                plane_id: kms.alloc_object_id(),
+                crtc_id: kms.alloc_object_id(),
+                encoder_id: kms.alloc_object_id(),
+                connector_id: kms.alloc_object_id(),
+                scanout,
+                crtc: CrtcState::default(),
+            };
+            kms.outputs.push(output);
+        }
+
+        Ok(kms)
+    }
+
+    fn alloc_object_id(&mut self) -> u32 {
+        let id = self.next_object_id;
+        self.next_object_id += 1;
+        id
+    }
+
+    /// Handles `DRM_IOCTL_MODE_GETRESOURCES`.
+    pub(super) fn get_resources(&self, file_id: u64, res: &mut DrmModeCardRes) -> Result<()> {
+        let fb_ids: Vec<u32> = self
+            .fbs
+            .values()
+            .filter(|fb| fb.owner == file_id)
+            .map(|fb| fb.id)
+            .collect();
+        let crtc_ids: Vec<u32> = self.outputs.iter().map(|output| output.crtc_id).collect();
+        let connector_ids: Vec<u32> = self
+            .outputs
+            .iter()
+            .map(|output| output.connector_id)
+            .collect();
+        let encoder_ids: Vec<u32> = self
+            .outputs
+            .iter()
+            .map(|output| output.encoder_id)
+            .collect();
+
+        write_array(res.fb_id_ptr, &mut res.count_fbs, &fb_ids)?;
+        write_array(res.crtc_id_ptr, &mut res.count_crtcs, &crtc_ids)?;
+        write_array(
+            res.connector_id_ptr,
+            &mut res.count_connectors,
+            &connector_ids,
+        )?;
+        write_array(res.encoder_id_ptr, &mut res.count_encoders, &encoder_ids)?;
+
+        res.min_width = MIN_SIZE;
+        res.max_width = MAX_SIZE;
+        res.min_height = MIN_SIZE;
+        res.max_height = MAX_SIZE;
+
+        Ok(())
+    }
+
+    /// Handles `DRM_IOCTL_MODE_GETCRTC`.
+    pub(super) fn get_crtc(&self, crtc_req: &mut DrmModeCrtc) -> Result<()> {
+        let output = self.output_by(crtc_req.crtc_id, |output| output.crtc_id)?;
+        let crtc = &output.crtc;
+
+        crtc_req.count_connectors = 0;
+        crtc_req.gamma_size = 0;
+        crtc_req.x = crtc.x;
+        crtc_req.y = crtc.y;
+        crtc_req.fb_id = crtc.fb.as_ref().map_or(0, |fb| fb.id);
+        match crtc.mode {
+            Some(mode) => {
+                crtc_req.mode_valid = 1;
+                crtc_req.mode = mode;
+            }
+            None => {
+                crtc_req.mode_valid = 0;
+                crtc_req.mode = DrmModeModeInfo::new_zeroed();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handles `DRM_IOCTL_MODE_SETCRTC`.
+    pub(super) fn set_crtc(&mut self, crtc_req: &DrmModeCrtc) -> Result<()> {
+        let index = self.output_index_by(crtc_req.crtc_id, |output| output.crtc_id)?;
+
+        let fb_and_mode = if crtc_req.mode_valid != 0 {
+            // An ID of `-1` means that the current framebuffer is kept.
+            let fb = if crtc_req.fb_id == u32::MAX {
+                self.outputs[index].crtc.fb.clone().ok_or_else(|| {
+                    Error::with_message(Errno::EINVAL, "the CRTC has no framebuffer")
+                })?
+            } else {
+                self.lookup_fb(crtc_req.fb_id)?
+            };
+
+            let mode = crtc_req.mode;
+            mode.check()?;
+            check_viewport(&fb, &mode, crtc_req.x, crtc_req.y)?;
+
+            Some((fb, mode))
+        } else {
+            None
+        };
+
+        if crtc_req.count_connectors == 0 && fb_and_mode.is_some() {
+            return_errno_with_message!(Errno::EINVAL, "the mode is set without connectors");
+        }
+        if crtc_req.count_connectors > 0 && fb_and_mode.is_none() {
+            return_errno_with_message!(Errno::EINVAL, "the connectors are set without a mode");
+        }
+        if crtc_req.count_connectors as usize > self.outputs.len() {
+            return_errno_with_message!(Errno::EINVAL, "there are too many connectors");
+        }
+
+        let user_space = current_userspace!();
+        for i in 0..crtc_req.count_connectors as usize {
+            let connector_id: u32 =
+                user_space.read_val(crtc_req.set_connectors_ptr as Vaddr + i * size_of::<u32>())?;
+            let connector_index =
+                self.output_index_by(connector_id, |output| output.connector_id)?;
+            // A connector can only be driven by the CRTC of the same scanout.
+            if connector_index != index {
+                return_errno_with_message!(
+                    Errno::EINVAL,
+                    "the connector cannot be driven by the CRTC"
+                );
+            }
+        }
+
+        match fb_and_mode {
+            Some((fb, mode)) => self.enable_output(index, fb, mode, crtc_req.x, crtc_req.y),
+            None => self.disable_output(index),
+        }
+    }
+
+    /// Handles `DRM_IOCTL_MODE_GETENCODER`.
+    pub(super) fn get_encoder(&self, encoder_req: &mut DrmModeGetEncoder) -> Result<()> {
+        let index = self.output_index_by(encoder_req.encoder_id, |output| output.encoder_id)?;
+        let output = &self.outputs[index];
+
+        encoder_req.encoder_type = DRM_MODE_ENCODER_VIRTUAL;
+        encoder_req.crtc_id = if output.crtc.fb.is_some() {
+            output.crtc_id
+        } else {
+            0
+        };
+        encoder_req.possible_crtcs = 1 << index;
+        encoder_req.possible_clones = 1 << index;
+
+        Ok(())
+    }
+
+    /// Handles `DRM_IOCTL_MODE_GETCONNECTOR`.
+    pub(super) fn get_connector(&mut self, connector_req: &mut DrmModeGetConnector) -> Result<()> {
+        let index =
+            self.output_index_by(connector_req.connector_id, |output| output.connector_id)?;
+
+        // Like Linux, the connectors are probed again if the user asks for the number of modes.
+        if connector_req.count_modes == 0 {
+            self.probe_scanouts()?;
+        }
+
+        let output = &self.outputs[index];
+        let scanout = &output.scanout;
+
+        let mut modes = Vec::new();
+        if scanout.is_enabled {
+            let (width, height) = if scanout.width != 0 && scanout.height != 0 {
+                (scanout.width.min(MAX_SIZE), scanout.height.min(MAX_SIZE))
+            } else {
+                DEFAULT_SIZE
+            };
+            modes.push(DrmModeModeInfo::new(width, height, true));
+            for (std_width, std_height) in STANDARD_SIZES {
+                if (std_width, std_height) != (width, height) {
+                    modes.push(DrmModeModeInfo::new(std_width, std_height, false));
+                }
+            }
+        }
+
+        write_array(
+            connector_req.modes_ptr,
+            &mut connector_req.count_modes,
+            &modes,
+        )?;
+        write_array(
+            connector_req.encoders_ptr,
+            &mut connector_req.count_encoders,
+            &[output.encoder_id],
+        )?;
+        // No properties are supported.
+        connector_req.count_props = 0;
+
+        connector_req.encoder_id = if output.crtc.fb.is_some() {
+            output.encoder_id
+        } else {
+            0
+        };
+        connector_req.connector_type = DRM_MODE_CONNECTOR_VIRTUAL;
+        connector_req.connector_type_id = index as u32 + 1;
+        connector_req.connection = if scanout.is_enabled {
+            DRM_MODE_CONNECTED
+        } else {
+            DRM_MODE_DISCONNECTED
+        };
+        connector_req.mm_width = 0;
+        connector_req.mm_height = 0;
+        connector_req.subpixel = 0;
+
+        Ok(())
+    }
+
+    fn probe_scanouts(&mut self) -> Result<()> {
+        let scanouts = self.gpu.display_info()?;
+        for (output, scanout) in self.outputs.iter_mut().zip(scanouts) {
+            output.scanout = scanout;
+        }
+        Ok(())
+    }
+
+    /// Handles `DRM_IOCTL_MODE_GETPLANERESOURCES`.
+    ///
+    /// The primary planes are only visible if the user has set
+    /// `DRM_CLIENT_CAP_UNIVERSAL_PLANES`.
+    pub(super) fn get_plane_resources(
+        &self,
+        is_universal_planes: bool,
+        res: &mut DrmModeGetPlaneRes,
+    ) -> Result<()> {
+        let plane_ids: Vec<u32> = if is_universal_planes {
+            self.outputs.iter().map(|output| output.plane_id).collect()
+        } else {
+            Vec::new()
+        };
+        write_array(res.plane_id_ptr, &mut res.count_planes, &plane_ids)
+    }
+
+    /// Handles `DRM_IOCTL_MODE_GETPLANE`.
+    pub(super) fn get_plane(&self, plane_req: &mut DrmModeGetPlane) -> Result<()> {
+        let index = self.output_index_by(plane_req.plane_id, |output| output.plane_id)?;
+        let output = &self.outputs[index];
+
+        match output.crtc.fb.as_ref() {
+            Some(fb) => {
+                plane_req.crtc_id = output.crtc_id;
+                plane_req.fb_id = fb.id;
+            }
+            None => {
+                plane_req.crtc_id = 0;
+                plane_req.fb_id = 0;
+            }
+        }
+        plane_req.possible_crtcs = 1 << index;
+        plane_req.gamma_size = 0;
+
+        write_array(
+            plane_req.format_type_ptr,
+            &mut plane_req.count_format_types,
+            &SUPPORTED_FORMATS,
+        )
+    }
+
+    /// Handles `DRM_IOCTL_MODE_OBJ_GETPROPERTIES`.
+    ///
+    /// No properties are supported, so only the existence of the object is checked.
+    pub(super) fn get_object_properties(&self, req: &mut DrmModeObjGetProperties) -> Result<()> {
+        let id = req.obj_id;
+        let type_ = req.obj_type;
+        let matches = |object_type: u32, object_id: u32| {
+            object_id == id && (type_ == DRM_MODE_OBJECT_ANY || type_ == object_type)
+        };
+
+        let is_found = self.outputs.iter().any(|output| {
+            matches(DRM_MODE_OBJECT_PLANE, output.plane_id)
+                || matches(DRM_MODE_OBJECT_CRTC, output.crtc_id)
+                || matches(DRM_MODE_OBJECT_ENCODER, output.encoder_id)
+                || matches(DRM_MODE_OBJECT_CONNECTOR, output.connector_id)
+        });
+        if !is_found {
+            if self
+                .fbs
+                .keys()
+                .any(|fb_id| matches(DRM_MODE_OBJECT_FB, *fb_id))
+            {
+                return_errno_with_message!(Errno::EINVAL, "the object has no properties");
+            }
+            return_errno_with_message!(Errno::ENOENT, "the object does not exist");
+        }
+
+        req.count_props = 0;
+        Ok(())
+    }
+
+    /// Handles `DRM_IOCTL_MODE_GETFB`.
+    ///
+    /// No handle is created for the buffer of the framebuffer, so the returned handle is zero.
+    pub(super) fn get_fb(&self, fb_req: &mut DrmModeFbCmd) -> Result<()> {
+        let fb = self.lookup_fb(fb_req.fb_id)?;
+
+        fb_req.width = fb.width;
+        fb_req.height = fb.height;
+        fb_req.pitch = fb.pitch;
+        fb_req.bpp = BYTES_PER_PIXEL * 8;
+        fb_req.depth = fb.depth();
+        fb_req.handle = 0;
+
+        Ok(())
+    }
+
+    /// Adds a framebuffer and returns its ID.
+    #[expect(clippy::too_many_arguments)]
+    pub(super) fn add_fb(
+        &mut self,
+        owner: u64,
+        buffer: Arc<DumbBuffer>,
+        width: u32,
+        height: u32,
+        pitch: u32,
+        offset: u32,
+        format: u32,
+    ) -> Result<u32> {
+        if !SUPPORTED_FORMATS.contains(&format) {
+            return_errno_with_message!(Errno::EINVAL, "the pixel format is not supported");
+        }
+
+        let size_range = MIN_SIZE..=MAX_SIZE;
+        if !size_range.contains(&width) || !size_range.contains(&height) {
+            return_errno_with_message!(Errno::EINVAL, "the framebuffer size is not supported");
+        }
+
+        let min_pitch = width * BYTES_PER_PIXEL;
+        if pitch < min_pitch {
+            return_errno_with_message!(Errno::EINVAL, "the pitch is too small");
+        }
+        let end = offset as u64 + pitch as u64 * (height - 1) as u64 + min_pitch as u64;
+        if end > buffer.size() as u64 {
+            return_errno_with_message!(Errno::EINVAL, "the buffer is too small");
+        }
+
+        let id = self.alloc_object_id();
+        let fb = Framebuffer {
+            id,
+            owner,
+            width,
+            height,
+            pitch,
+            offset,
+            format,
+            buffer,
+        };
+        self.fbs.insert(id, Arc::new(fb));
+
+        Ok(id)
+    }
+
+    /// Removes a framebuffer, which must be created by the file.
+    ///
+    /// The CRTCs that display the framebuffer are disabled.
+    pub(super) fn remove_fb(&mut self, file_id: u64, fb_id: u32) -> Result<()> {
+        let is_owned = self.fbs.get(&fb_id).is_some_and(|fb| fb.owner == file_id);
+        if !is_owned {
+            return_errno_with_message!(Errno::ENOENT, "the framebuffer does not exist");
+        }
+
+        self.fbs.remove(&fb_id);
+        for index in 0..self.outputs.len() {
+            let is_displayed = self.outputs[index]
+                .crtc
+                .fb
+                .as_ref()
+                .is_some_and(|fb| fb.id == fb_id);
+            if is_displayed {
+                self.disable_output(index)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes all the framebuffers created by the file.
+    pub(super) fn remove_fbs_of(&mut self, file_id: u64) {
+        let fb_ids: Vec<u32> = self
+            .fbs
+            .values()
+            .filter(|fb| fb.owner == file_id)
+            .map(|fb| fb.id)
+            .collect();
+        for fb_id in fb_ids {
+            if let Err(err) = self.remove_fb(file_id, fb_id) {
+                warn!("failed to remove the framebuffer {}: {:?}", fb_id, err);
+            }
+        }
+    }
+
+    /// Handles `DRM_IOCTL_MODE_PAGE_FLIP`.
+    ///
+    /// The flip is completed before this method returns.
+    pub(super) fn page_flip(&mut self, flip_req: &DrmModeCrtcPageFlip) -> Result<PageFlipInfo> {
+        if flip_req.flags & !(DRM_MODE_PAGE_FLIP_EVENT | DRM_MODE_PAGE_FLIP_ASYNC) != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the page flip flags are invalid");
+        }
+        if flip_req.flags & DRM_MODE_PAGE_FLIP_ASYNC != 0 {
+            return_errno_with_message!(Errno::EINVAL, "asynchronous page flips are not supported");
+        }
+        if flip_req.reserved != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the reserved field is not zero");
+        }
+
+        let index = self.output_index_by(flip_req.crtc_id, |output| output.crtc_id)?;
+        let Some(old_fb) = self.outputs[index].crtc.fb.clone() else {
+            return_errno_with_message!(Errno::EBUSY, "the CRTC is disabled");
+        };
+        let fb = self.lookup_fb(flip_req.fb_id)?;
+        if fb.format != old_fb.format {
+            return_errno_with_message!(Errno::EINVAL, "the pixel format cannot be changed");
+        }
+
+        let crtc = &mut self.outputs[index].crtc;
+        check_viewport(&fb, crtc.mode.as_ref().unwrap(), crtc.x, crtc.y)?;
+        crtc.fb = Some(fb);
+
+        self.present(index, None)?;
+
+        let output = &mut self.outputs[index];
+        output.crtc.sequence = output.crtc.sequence.wrapping_add(1);
+        Ok(PageFlipInfo {
+            crtc_id: output.crtc_id,
+            sequence: output.crtc.sequence,
+        })
+    }
+
+    /// Handles `DRM_IOCTL_MODE_DIRTYFB`.
+    pub(super) fn dirty_fb(&mut self, dirty_req: &DrmModeFbDirtyCmd) -> Result<()> {
+        let fb = self.lookup_fb(dirty_req.fb_id)?;
+
+        let flags = dirty_req.flags;
+        let num_clips = dirty_req.num_clips;
+        if flags & !(DRM_MODE_FB_DIRTY_ANNOTATE_COPY | DRM_MODE_FB_DIRTY_ANNOTATE_FILL) != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the dirty flags are invalid");
+        }
+        if flags == DRM_MODE_FB_DIRTY_ANNOTATE_COPY | DRM_MODE_FB_DIRTY_ANNOTATE_FILL {
+            return_errno_with_message!(Errno::EINVAL, "the dirty flags are exclusive");
+        }
+        if num_clips > DRM_MODE_FB_DIRTY_MAX_CLIPS {
+            return_errno_with_message!(Errno::EINVAL, "there are too many clips");
+        }
+        if flags & DRM_MODE_FB_DIRTY_ANNOTATE_COPY != 0 && num_clips % 2 != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the clips are not in pairs");
+        }
+        if num_clips > 0 && dirty_req.clips_ptr == 0 {
+            return_errno_with_message!(Errno::EINVAL, "the clips are missing");
+        }
+
+        // Like Linux, the clips are merged into one rectangle.
+        let mut damage = if num_clips == 0 {
+            Some((0, 0, fb.width, fb.height))
+        } else {
+            None
+        };
+        let user_space = current_userspace!();
+        for i in 0..num_clips as usize {
+            let clip: DrmClipRect =
+                user_space.read_val(dirty_req.clips_ptr as Vaddr + i * size_of::<DrmClipRect>())?;
+            let (x1, y1) = (clip.x1 as u32, clip.y1 as u32);
+            let (x2, y2) = (clip.x2 as u32, clip.y2 as u32);
+            damage = Some(match damage {
+                Some((dx1, dy1, dx2, dy2)) => (dx1.min(x1), dy1.min(y1), dx2.max(x2), dy2.max(y2)),
+                None => (x1, y1, x2, y2),
+            });
+        }
+        let Some((x1, y1, x2, y2)) = damage else {
+            return Ok(());
+        };
+
+        for index in 0..self.outputs.len() {
+            let crtc = &self.outputs[index].crtc;
+            let is_displayed = crtc.fb.as_ref().is_some_and(|crtc_fb| crtc_fb.id == fb.id);
+            if !is_displayed {
+                continue;
+            }
+
+            // Convert the damage to the coordinates of the CRTC.
+            let mode = crtc.mode.as_ref().unwrap();
+            let left = x1.max(crtc.x) - crtc.x;
+            let top = y1.max(crtc.y) - crtc.y;
+            let right = x2.min(crtc.x + mode.width()).saturating_sub(crtc.x);
+            let bottom = y2.min(crtc.y + mode.height()).saturating_sub(crtc.y);
+            if left >= right || top >= bottom {
+                continue;
+            }
+
+            self.present(
+                index,
+                Some(Rect::new(left, top, right - left, bottom - top)),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn enable_output(
+        &mut self,
+        index: usize,
+        fb: Arc<Framebuffer>,
+        mode: DrmModeModeInfo,
+        x: u32,
+        y: u32,
+    ) -> Result<()> {
+        let (width, height) = (mode.width(), mode.height());
+        let output = &mut self.outputs[index];
+
+        let is_reusable = output
+            .crtc
+            .resource
+            .as_ref()
+            .is_some_and(|resource| resource.width == width && resource.height == height);
+        if !is_reusable {
+            let resource = create_host_resource(&self.gpu, width, height)?;
+            let rect = Rect::new(0, 0, width, height);
+            if let Err(err) = self.gpu.set_scanout(output.scanout_id, resource.id, rect) {
+                release_host_resource(&self.gpu, resource);
+                return Err(err.into());
+            }
+            if let Some(old_resource) = output.crtc.resource.replace(resource) {
+                release_host_resource(&self.gpu, old_resource);
+            }
+        }
+
+        let crtc = &mut output.crtc;
+        crtc.fb = Some(fb);
+        crtc.mode = Some(mode);
+        crtc.x = x;
+        crtc.y = y;
+
+        self.present(index, None)
+    }
+
+    fn disable_output(&mut self, index: usize) -> Result<()> {
+        let output = &mut self.outputs[index];
+        let crtc = &mut output.crtc;
+        crtc.fb = None;
+        crtc.mode = None;
+        crtc.x = 0;
+        crtc.y = 0;
+
+        let Some(resource) = crtc.resource.take() else {
+            return Ok(());
+        };
+        let result = self.gpu.set_scanout(output.scanout_id, 0, Rect::default());
+        release_host_resource(&self.gpu, resource);
+
+        Ok(result?)
+    }
+
+    /// Copies the framebuffer of the CRTC to the host and displays it.
+    ///
+    /// `rect` is the region to update in the coordinates of the CRTC. If it is `None`, the whole
+    /// CRTC is updated.
+    fn present(&self, index: usize, rect: Option<Rect>) -> Result<()> {
+        let crtc = &self.outputs[index].crtc;
+        let fb = crtc.fb.as_ref().unwrap();
+        let resource = crtc.resource.as_ref().unwrap();
+
+        let rect = rect.unwrap_or(Rect::new(0, 0, resource.width, resource.height));
+        fb.copy_to(
+            &resource.backing,
+            resource.width,
+            crtc.x + rect.x,
+            crtc.y + rect.y,
+            rect,
+        );
+
+        let offset = ((rect.y * resource.width + rect.x) * BYTES_PER_PIXEL) as u64;
+        self.gpu.transfer_to_host_2d(resource.id, rect, offset)?;
+        self.gpu.flush_resource(resource.id, rect)?;
+
+        Ok(())
+    }
+
+    fn lookup_fb(&self, fb_id: u32) -> Result<Arc<Framebuffer>> {
+        self.fbs
+            .get(&fb_id)
+            .cloned()
+            .ok_or_else(|| Error::with_message(Errno::ENOENT, "the framebuffer does not exist"))
+    }
+
+    fn output_by(&self, id: u32, get_id: impl Fn(&Output) -> u32) -> Result<&Output> {
+        let index = self.output_index_by(id, get_id)?;
+        Ok(&self.outputs[index])
+    }
+
+    fn output_index_by(&self, id: u32, get_id: impl Fn(&Output) -> u32) -> Result<usize> {
+        self.outputs
+            .iter()
+            .position(|output| get_id(output) == id)
+            .ok_or_else(|| Error::with_message(Errno::ENOENT, "the mode object does not exist"))
+    }
+}
+
+impl Drop for Kms {
+    fn drop(&mut self) {
+        for index in 0..self.outputs.len() {
+            let _ = self.disable_output(index);
+        }
+    }
+}
+
+/// Checks whether the framebuffer is large enough for the mode at the position.
+fn check_viewport(fb: &Framebuffer, mode: &DrmModeModeInfo, x: u32, y: u32) -> Result<()> {
+    let fits = x as u64 + mode.width() as u64 <= fb.width as u64
+        && y as u64 + mode.height() as u64 <= fb.height as u64;
+    if !fits {
+        return_errno_with_message!(Errno::ENOSPC, "the framebuffer is too small for the mode");
+    }
+
+    Ok(())
+}
+
+fn create_host_resource(gpu: &GpuDevice, width: u32, height: u32) -> Result<HostResource> {
+    let size = (width * height * BYTES_PER_PIXEL) as usize;
+    let backing = DmaStream::alloc(size.div_ceil(PAGE_SIZE), false)?;
+
+    // The pixels of `DRM_FORMAT_XRGB8888` are stored as blue, green, red, and unused bytes.
+    let id = gpu.create_resource_2d(ResourceFormat::B8G8R8X8Unorm, width, height)?;
+    if let Err(err) = gpu.attach_backing(id, &[(backing.daddr(), backing.size())]) {
+        let _ = gpu.unref_resource(id);
+        return Err(err.into());
+    }
+
+    Ok(HostResource {
+        id,
+        width,
+        height,
+        backing,
+    })
+}
+
+fn release_host_resource(gpu: &GpuDevice, resource: HostResource) {
+    let result = gpu
+        .detach_backing(resource.id)
+        .and_then(|_| gpu.unref_resource(resource.id));
+    if let Err(err) = result {
+        warn!("failed to release the resource {}: {:?}", resource.id, err);
+    }
+}
+
+/// Writes the items to the user array and updates the count, as Linux does.
+///
+/// The items are written only if the array is large enough to hold all of them.
+fn write_array<T: Pod>(ptr: u64, count: &mut u32, items: &[T]) -> Result<()> {
+    if !items.is_empty() && *count as usize >= items.len() {
+        current_userspace!().write_bytes(ptr as Vaddr, items.as_bytes())?;
+    }
+    *count = items.len() as u32;
+
+    Ok(())
+}
@@ -0,0 +1,97 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Direct Rendering Manager (DRM) devices (`/dev/dri/card<N>`).
+//!
+//! Each virtio GPU device is exposed as a DRM device that supports kernel mode setting (KMS) and
+//! dumb buffers, which are enough for the software-rendering display servers. Rendering with the
+//! GPU (i.e., 3D acceleration) is not supported.
+//!
+//! Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/gpu/drm/virtio/virtgpu_drv.c>.
+
+mod file;
+mod gem;
+mod ioctl_defs;
+mod kms;
+
+use alloc::format;
+
+use aster_virtio::device::gpu::{self, device::GpuDevice};
+use device_id::{DeviceId, MajorId, MinorId};
+use spin::Once;
+
+use self::{file::DrmFile, gem::GemSpace, kms::Kms};
+use super::{
+    Device, DeviceType,
+    registry::char::{self, MajorIdOwner},
+};
+use crate::{fs::file::FileIo, prelude::*};
+
+/// The major device number of DRM devices.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/major.h>.
+const DRM_MAJOR_ID: u16 = 226;
+
+static DRM_MAJOR: Once<MajorIdOwner> = Once::new();
+
+struct DrmDevice {
+    id: DeviceId,
+    kms: Mutex<Kms>,
+    gem: Arc<GemSpace>,
+    /// The ID of the file that is the master, i.e., the file that can change the modes
+    master: Mutex<Option<u64>>,
+    weak_self: Weak<Self>,
+}
+
+impl DrmDevice {
+    fn new(gpu: Arc<GpuDevice>, minor: u32) -> Result<Arc<Self>> {
+        let major = DRM_MAJOR.get().unwrap().get();
+        let kms = Kms::new(gpu)?;
+        let gem = GemSpace::new()?;
+
+        Ok(Arc::new_cyclic(|weak_self| Self {
+            id: DeviceId::new(major, MinorId::new(minor)),
+            kms: Mutex::new(kms),
+            gem,
+            master: Mutex::new(None),
+            weak_self: weak_self.clone(),
+        }))
+    }
+}
+
+impl Device for DrmDevice {
+    fn type_(&self) -> DeviceType {
+        DeviceType::Char
+    }
+
+    fn id(&self) -> DeviceId {
+        self.id
+    }
+
+    fn devtmpfs_path(&self) -> Option<String> {
+        Some(format!("dri/card{}", self.id.minor().get()))
+    }
+
+    fn class(&self) -> &'static str {
+        "drm"
+    }
+
+    fn open(&self) -> Result<Box<dyn FileIo>> {
+        let device = self.weak_self.upgrade().unwrap();
+        Ok(Box::new(DrmFile::new(device)))
+    }
+}
+
+pub(super) fn init_in_first_kthread() {
+    DRM_MAJOR.call_once(|| char::acquire_major(MajorId::new(DRM_MAJOR_ID)).unwrap());
+
+    for (index, gpu) in gpu::all_devices().into_iter().enumerate() {
+        if let Err(err) = add_device(gpu, index as u32) {
+            warn!("failed to add the DRM device for the virtio GPU: {:?}", err);
+        }
+    }
+}
+
+fn add_device(gpu: Arc<GpuDevice>, minor: u32) -> Result<()> {
+    let device = DrmDevice::new(gpu, minor)?;
+    char::register(device)
+}
@@ -38,7 +38,7 @@ pub(super) const EVDEV_BUFFER_SIZE: usize = 64;
 mod ioctl_defs {
     use aster_input::input_dev::InputId;
 
-    use crate::util::ioctl::{InData, IoctlEnum, OutData, ioc};
+    use crate::util::ioctl::{InData, IoctlEnum, OutData, PassByVal, ioc};
 
     // Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/input.h>
 
@@ -47,13 +47,17 @@ mod ioctl_defs {
     pub(super) type GetDeviceName = ioc!(EVIOCGNAME,    b'E', 0x06, OutData<[u8]>);
     pub(super) type GetDevicePhys = ioc!(EVIOCGPHYS,    b'E', 0x07, OutData<[u8]>);
     pub(super) type GetDeviceUniq = ioc!(EVIOCGUNIQ,    b'E', 0x08, OutData<[u8]>);
+    pub(super) type GetDeviceProp = ioc!(EVIOCGPROP,    b'E', 0x09, OutData<[u8]>);
     pub(super) type GetKeyState   = ioc!(EVIOCGKEY,     b'E', 0x18, OutData<[u8]>);
     pub(super) type GetLedState   = ioc!(EVIOCGLED,     b'E', 0x19, OutData<[u8]>);
     pub(super) type GetSwState    = ioc!(EVIOCGSW,      b'E', 0x1B, OutData<[u8]>);
+    pub(super) type Grab          = ioc!(EVIOCGRAB,     b'E', 0x90, InData<i32, PassByVal>);
     pub(super) type SetClockId    = ioc!(EVIOCSCLOCKID, b'E', 0xA0, InData<i32>);
 
     /// The `EVIOCGBIT` ioctl enum.
     pub(super) type GetEventBits = IoctlEnum<b'E', 0x20, 0x1F, OutData<[u8]>>;
+    /// The `EVIOCGABS` ioctl enum.
+    pub(super) type GetAbsInfo = IoctlEnum<b'E', 0x40, 0x3F, OutData<[u8]>>;
 }
 
 // Reference: <https://elixir.bootlin.com/linux/v6.17.9/source/include/uapi/linux/input.h#L28>
@@ -333,8 +337,11 @@ fn handle_get_bit(evdev: &Arc<EvdevDevice>, event_type: u8, writer: &mut VmWrite
             let bitmap = capability.supported_relative_axes_bitmap();
             write_bytes_and_zeros_to_userspace(writer, bitmap)?;
         }
-        t if t == EventTypes::ABS.as_index()
-            || t == EventTypes::LED.as_index()
+        t if t == EventTypes::ABS.as_index() => {
+            let bitmap = capability.supported_absolute_axes_bitmap();
+            write_bytes_and_zeros_to_userspace(writer, bitmap)?;
+        }
+        t if t == EventTypes::LED.as_index()
             || t == EventTypes::SW.as_index()
             || t == EventTypes::MSC.as_index()
             || t == EventTypes::FF.as_index()
@@ -456,12 +463,51 @@ impl FileIo for EvdevFile {
                     write_bytes_and_zeros_to_userspace(&mut writer, evdev.device.uniq().as_bytes())
                 })?;
             }
+            cmd @ GetDeviceProp => {
+                let evdev = self.upgrade_device()?;
+                let props = evdev.device.capability().properties().bits();
+                cmd.with_writer(|mut writer| {
+                    write_bytes_and_zeros_to_userspace(&mut writer, &props.to_ne_bytes())
+                })?;
+            }
             cmd @ GetEventBits => {
                 let evdev = self.upgrade_device()?;
                 let event_type = cmd.discriminant();
                 cmd.base_ioctl()
                     .with_writer(|mut writer| handle_get_bit(&evdev, event_type, &mut writer))?;
             }
+            cmd @ GetAbsInfo => {
+                let evdev = self.upgrade_device()?;
+                if !evdev
+                    .device
+                    .capability()
+                    .support_event_type(EventTypes::ABS)
+                {
+                    return_errno_with_message!(
+                        Errno::EINVAL,
+                        "the evdev device does not support absolute axes"
+                    );
+                }
+
+                // Linux copies no more than the size of the structure. Older user space programs
+                // may use a smaller structure without the `resolution` field.
+                let abs_info = evdev.abs_info(cmd.discriminant() as u16);
+                cmd.base_ioctl().with_writer(|mut writer| {
+                    writer.write_fallible(&mut abs_info.as_bytes().into())?;
+                    Ok(())
+                })?;
+            }
+            cmd @ Grab => {
+                let evdev = self.upgrade_device()?;
+                if cmd.get() != 0 {
+                    evdev.grab(&self.inner)?;
+                } else if !evdev.ungrab(&self.inner) {
+                    return_errno_with_message!(
+                        Errno::EINVAL,
+                        "the evdev file has not grabbed the device"
+                    );
+                }
+            }
             cmd @ GetKeyState => {
                 // TODO: We need to track whether the key is currently pressed and report that state
                 // here. If we report states, we need to flush the queue to avoid interfering with the
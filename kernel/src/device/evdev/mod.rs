@@ -17,8 +17,8 @@ use core::{
 
 use aster_input::{
     event_type_codes::SynEvent,
-    input_dev::{InputDevice, InputEvent},
-    input_handler::{ConnectError, InputHandler, InputHandlerClass},
+    input_dev::{AbsInfo, InputDevice, InputEvent},
+    input_handler::{ConnectError, GrabError, InputHandler, InputHandlerClass},
 };
 use device_id::{DeviceId, MajorId, MinorId};
 use file::{
@@ -54,6 +54,20 @@ struct EvdevDevice {
     /// We must make sure that this lock is taken with the local IRQs disabled.
     /// Otherwise, we would be vulnerable to deadlock.
     opened_files: SpinLock<Vec<(Arc<EvdevFileInner>, RbProducer<EvdevEvent>)>>,
+    /// The opened evdev file that has grabbed the device.
+    ///
+    /// While the device is grabbed, events are delivered only to this file.
+    ///
+    /// # Deadlock Prevention
+    ///
+    /// Like `opened_files`, this lock must be taken with the local IRQs disabled.
+    grab: SpinLock<Option<Arc<EvdevFileInner>>>,
+    /// Parameters and latest values of the absolute axes, indexed by the axis codes.
+    ///
+    /// # Deadlock Prevention
+    ///
+    /// Like `opened_files`, this lock must be taken with the local IRQs disabled.
+    abs_infos: SpinLock<BTreeMap<u16, AbsInfo>>,
     /// Device ID.
     id: DeviceId,
 }
@@ -76,9 +90,17 @@ impl EvdevDevice {
         let major = MajorId::new(EVDEV_MAJOR_ID);
         let minor_id = MinorId::new(minor);
 
+        let abs_infos = device
+            .capability()
+            .absolute_axis_infos()
+            .map(|(abs_code, abs_info)| (abs_code as u16, *abs_info))
+            .collect();
+
         Self {
             device,
             opened_files: SpinLock::new(Vec::new()),
+            grab: SpinLock::new(None),
+            abs_infos: SpinLock::new(abs_infos),
             id: DeviceId::new(major, minor_id),
         }
     }
@@ -95,7 +117,9 @@ impl EvdevDevice {
     }
 
     /// Removes the closed evdev file from this evdev device.
-    pub(self) fn detach_closed_file(&self, file: &Arc<EvdevFileInner>) {
+    pub(self) fn detach_closed_file(self: &Arc<Self>, file: &Arc<EvdevFileInner>) {
+        self.ungrab(file);
+
         let mut opened_files = self.opened_files.disable_irq().lock();
         let pos = opened_files
             .iter()
@@ -104,6 +128,56 @@ impl EvdevDevice {
         opened_files.swap_remove(pos);
     }
 
+    /// Grabs the device so that its events are delivered only to the evdev file.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/input/evdev.c>
+    pub(self) fn grab(self: &Arc<Self>, file: &Arc<EvdevFileInner>) -> Result<()> {
+        // Grabbing the input device prevents other handlers (e.g., the VT keyboard) from
+        // receiving the events.
+        let handler: Arc<dyn InputHandler> = self.clone();
+        match aster_input::grab_device(&self.device, &handler) {
+            Ok(()) => (),
+            Err(GrabError::AlreadyGrabbed) => {
+                return_errno_with_message!(Errno::EBUSY, "the input device is already grabbed")
+            }
+            Err(GrabError::NotConnected) => {
+                return_errno_with_message!(Errno::ENODEV, "the evdev device has been disconnected")
+            }
+        }
+
+        *self.grab.disable_irq().lock() = Some(file.clone());
+
+        Ok(())
+    }
+
+    /// Releases the device if it has been grabbed by the evdev file.
+    ///
+    /// Returns whether the device has been released.
+    pub(self) fn ungrab(self: &Arc<Self>, file: &Arc<EvdevFileInner>) -> bool {
+        let mut grab = self.grab.disable_irq().lock();
+        if !grab
+            .as_ref()
+            .is_some_and(|grab_file| Arc::ptr_eq(grab_file, file))
+        {
+            return false;
+        }
+        *grab = None;
+        drop(grab);
+
+        let handler: Arc<dyn InputHandler> = self.clone();
+        aster_input::release_device(&self.device, &handler);
+
+        true
+    }
+
+    /// Returns the parameters and the latest value of an absolute axis.
+    ///
+    /// If the device does not support the axis, zeros will be returned.
+    pub(self) fn abs_info(&self, abs_code: u16) -> AbsInfo {
+        let abs_infos = self.abs_infos.disable_irq().lock();
+        abs_infos.get(&abs_code).copied().unwrap_or_default()
+    }
+
     pub(self) fn with_producer_locked<F>(&self, file: &Arc<EvdevFileInner>, f: F)
     where
         F: FnOnce(&mut RbProducer<EvdevEvent>),
@@ -119,10 +193,29 @@ impl EvdevDevice {
     /// Distributes events to all opened evdev files.
     fn pass_events(&self, events: &[InputEvent]) {
         // No need to disable IRQs because this method can only be called in the interrupt context.
+        let mut abs_infos = self.abs_infos.lock();
+        for event in events {
+            let InputEvent::Absolute(abs_code, value) = event else {
+                continue;
+            };
+            if let Some(abs_info) = abs_infos.get_mut(&(*abs_code as u16)) {
+                abs_info.value = *value;
+            }
+        }
+        drop(abs_infos);
+
+        let grab = self.grab.lock().clone();
         let mut opened_files = self.opened_files.lock();
 
-        // Send events to all opened evdev files using their producers.
+        // Send events to all opened evdev files (or only the grabbing file) using their producers.
         for (file, producer) in opened_files.iter_mut() {
+            if grab
+                .as_ref()
+                .is_some_and(|grab_file| !Arc::ptr_eq(grab_file, file))
+            {
+                continue;
+            }
+
             for event in events {
                 // Read the current time according to the opened evdev file's clock type.
                 let time = file.read_clock();
@@ -201,6 +294,10 @@ impl Device for EvdevDevice {
         Some(format!("input/event{}", self.id.minor().get()))
     }
 
+    fn class(&self) -> &'static str {
+        "input"
+    }
+
     fn open(&self) -> Result<Box<dyn FileIo>> {
         // Get the device from the registry.
         let devices = EVDEV_DEVICES.lock();
@@ -199,8 +199,20 @@ struct FbCmapUser {
     pub transp: usize,
 }
 
+/// The mapping from a virtual console to a framebuffer; `struct fb_con2fbmap` in Linux.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.17/source/include/uapi/linux/fb.h>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct FbCon2FbMap {
+    /// The number of the virtual console (starting from 1)
+    pub console: u32,
+    /// The index of the framebuffer
+    pub framebuffer: u32,
+}
+
 mod ioctl_defs {
-    use super::{FbCmapUser, FbFixScreenInfo, FbVarScreenInfo};
+    use super::{FbCmapUser, FbCon2FbMap, FbFixScreenInfo, FbVarScreenInfo};
     use crate::util::ioctl::{InData, InOutData, NoData, OutData, ioc};
 
     // Reference: <https://elixir.bootlin.com/linux/v6.17/source/include/uapi/linux/fb.h#L13-L38>
@@ -210,6 +222,8 @@ mod ioctl_defs {
     pub(super) type GetFixScreenInfo = ioc!(FBIOGET_FSCREENINFO, 0x4602, OutData<FbFixScreenInfo>);
     pub(super) type GetColorMap      = ioc!(FBIOGETCMAP,         0x4604, InData<FbCmapUser>);
     pub(super) type PutColorMap      = ioc!(FBIOPUTCMAP,         0x4605, InData<FbCmapUser>);
+    pub(super) type GetCon2FbMap     = ioc!(FBIOGET_CON2FBMAP,   0x460F, InOutData<FbCon2FbMap>);
+    pub(super) type PutCon2FbMap     = ioc!(FBIOPUT_CON2FBMAP,   0x4610, InData<FbCon2FbMap>);
 
     // `NoData` is used below because they're not supported by efifb.
     pub(super) type PanDisplay       = ioc!(FBIOPAN_DISPLAY,     0x4606, NoData);
@@ -230,6 +244,10 @@ impl Device for Fb {
         Some("fb0".into())
     }
 
+    fn class(&self) -> &'static str {
+        "graphics"
+    }
+
     fn open(&self) -> Result<Box<dyn FileIo>> {
         let Some(framebuffer) = FRAMEBUFFER.get() else {
             return Err(Error::with_message(
@@ -498,6 +516,23 @@ impl FileIo for FbHandle {
                 self.handle_set_cmap(&cmd.read()?)?;
                 Ok(0)
             }
+            cmd @ GetCon2FbMap => {
+                let mut con2fb = cmd.read()?;
+                check_console_number(con2fb.console)?;
+                // All virtual consoles are rendered onto the only framebuffer.
+                con2fb.framebuffer = 0;
+                cmd.write(&con2fb)?;
+                Ok(0)
+            }
+            cmd @ PutCon2FbMap => {
+                let con2fb = cmd.read()?;
+                check_console_number(con2fb.console)?;
+                if con2fb.framebuffer != 0 {
+                    return_errno_with_message!(Errno::EINVAL, "the framebuffer does not exist");
+                }
+                // Mapping a virtual console to the only framebuffer changes nothing.
+                Ok(0)
+            }
             PanDisplay | Blank => {
                 // These commands are not supported by efifb.
                 // We return errors according to the Linux behavior.
@@ -517,6 +552,19 @@ impl FileIo for FbHandle {
     }
 }
 
+/// Checks the virtual console number in the console-to-framebuffer mapping.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.17/source/drivers/video/fbdev/core/fbcon.c>.
+fn check_console_number(console: u32) -> Result<()> {
+    /// The maximum number of virtual consoles; `MAX_NR_CONSOLES` in Linux.
+    const MAX_NR_CONSOLES: u32 = 63;
+
+    if !(1..=MAX_NR_CONSOLES).contains(&console) {
+        return_errno_with_message!(Errno::EINVAL, "the virtual console number is invalid");
+    }
+    Ok(())
+}
+
 pub(super) fn init_in_first_kthread() {
     if FRAMEBUFFER.get().is_none() {
         return;
@@ -0,0 +1,652 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Loop devices.
+//!
+//! A loop device (`/dev/loop<N>`) maps a regular file to a block device, so that a disk image (or
+//! a squashfs file) can be mounted like a real disk. The backing file is attached and configured
+//! by the ioctls on the loop device, whereas loop devices are created and removed by the ioctls on
+//! `/dev/loop-control`.
+//!
+//! Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/block/loop.c>
+
+use aster_block::{
+    BlockDevice, BlockDeviceMeta, EXTENDED_DEVICE_ID_ALLOCATOR, MajorIdOwner, PartitionInfo,
+    PartitionNode, SECTOR_SIZE,
+    bio::{BioEnqueueError, BioSegment, BioStatus, BioType, SubmittedBio},
+    partition_name,
+};
+use device_id::{DeviceId, MajorId, MinorId};
+use ostd::{mm::io::util::HasVmReaderWriter, task::Task};
+use spin::Once;
+
+use super::registry::block;
+use crate::{
+    fs::{
+        file::{FileLike, InodeType, StatusFlags, file_table::FileDesc},
+        vfs::inode::FallocMode,
+    },
+    prelude::*,
+    util::ioctl::{RawIoctl, dispatch_ioctl},
+};
+
+/// The major device ID of loop devices.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/major.h#L12>.
+const LOOP_MAJOR: u16 = 7;
+
+/// The number of loop devices created at boot.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/block/Kconfig#L216>.
+const NR_INITIAL_LOOP_DEVICES: u32 = 8;
+
+const LO_NAME_SIZE: usize = 64;
+const LO_KEY_SIZE: usize = 32;
+
+static LOOP_MAJOR_OWNER: Once<MajorIdOwner> = Once::new();
+
+static LOOP_DEVICES: Mutex<BTreeMap<u32, Arc<LoopDevice>>> = Mutex::new(BTreeMap::new());
+
+pub(super) fn init_in_first_kthread() {
+    LOOP_MAJOR_OWNER.call_once(|| aster_block::acquire_major(MajorId::new(LOOP_MAJOR)).unwrap());
+
+    for index in 0..NR_INITIAL_LOOP_DEVICES {
+        add_device(Some(index)).unwrap();
+    }
+}
+
+/// Adds a new loop device, returning its index.
+///
+/// If `index` is `None`, the first unused index will be chosen.
+pub(super) fn add_device(index: Option<u32>) -> Result<u32> {
+    let mut devices = LOOP_DEVICES.lock();
+
+    let index = match index {
+        Some(index) if devices.contains_key(&index) => {
+            return_errno_with_message!(Errno::EEXIST, "the loop device already exists");
+        }
+        Some(index) => index,
+        None => (0..).find(|index| !devices.contains_key(index)).unwrap(),
+    };
+    if index > MinorId::MAX.get() {
+        return_errno_with_message!(Errno::EINVAL, "the loop device index is too large");
+    }
+
+    let device = LoopDevice::new(index);
+    block::register(device.clone())?;
+    devices.insert(index, device);
+
+    Ok(index)
+}
+
+/// Removes an unbound loop device.
+pub(super) fn remove_device(index: u32) -> Result<()> {
+    let mut devices = LOOP_DEVICES.lock();
+
+    let Some(device) = devices.get(&index) else {
+        return_errno_with_message!(Errno::ENODEV, "the loop device does not exist");
+    };
+    if device.backing().is_some() {
+        return_errno_with_message!(Errno::EBUSY, "the loop device is bound to a file");
+    }
+
+    block::unregister(device.id)?;
+    devices.remove(&index);
+
+    Ok(())
+}
+
+/// Returns the index of an unbound loop device, creating a new one if all are bound.
+pub(super) fn get_free_device() -> Result<u32> {
+    if let Some(index) = LOOP_DEVICES
+        .lock()
+        .values()
+        .find(|device| device.backing().is_none())
+        .map(|device| device.index)
+    {
+        return Ok(index);
+    }
+
+    add_device(None)
+}
+
+/// A loop device.
+pub struct LoopDevice {
+    index: u32,
+    id: DeviceId,
+    name: String,
+    backing: Mutex<Option<Arc<Backing>>>,
+    partitions: Mutex<Vec<Arc<PartitionNode>>>,
+    weak_self: Weak<Self>,
+}
+
+impl LoopDevice {
+    fn new(index: u32) -> Arc<Self> {
+        let major = LOOP_MAJOR_OWNER.get().unwrap().get();
+        let id = DeviceId::new(major, MinorId::new(index));
+
+        Arc::new_cyclic(|weak_self| Self {
+            index,
+            id,
+            name: format!("loop{}", index),
+            backing: Mutex::new(None),
+            partitions: Mutex::new(Vec::new()),
+            weak_self: weak_self.clone(),
+        })
+    }
+
+    fn backing(&self) -> Option<Arc<Backing>> {
+        self.backing.lock().clone()
+    }
+
+    fn backing_or_err(&self) -> Result<Arc<Backing>> {
+        self.backing().ok_or_else(|| {
+            Error::with_message(Errno::ENXIO, "the loop device is not bound to a file")
+        })
+    }
+
+    /// Handles the ioctls of the loop device.
+    pub(super) fn ioctl(&self, raw_ioctl: RawIoctl) -> Result<i32> {
+        use ioctl_defs::*;
+
+        dispatch_ioctl!(match raw_ioctl {
+            cmd @ SetFd => {
+                self.bind(cmd.get(), 0, None)?;
+            }
+            _cmd @ ClrFd => {
+                self.unbind()?;
+            }
+            cmd @ SetStatus64 => {
+                self.set_status(&cmd.read()?)?;
+            }
+            cmd @ GetStatus64 => {
+                cmd.write(&self.backing_or_err()?.info(self.index))?;
+            }
+            _cmd @ SetCapacity => {
+                self.update_backing(|_| Ok(()))?;
+            }
+            cmd @ SetDirectIo => {
+                let is_enabled = cmd.get() != 0;
+                self.update_backing(|backing| {
+                    backing.flags.set(LoopFlags::DIRECT_IO, is_enabled);
+                    backing.check_direct_io()
+                })?;
+            }
+            cmd @ SetBlockSize => {
+                let block_size = check_block_size(cmd.get())?;
+                self.update_backing(|backing| {
+                    backing.block_size = block_size;
+                    backing.check_direct_io()
+                })?;
+            }
+            cmd @ Configure => {
+                let config = cmd.read()?;
+                self.bind(config.fd as FileDesc, config.block_size, Some(&config.info))?;
+            }
+            _ => return_errno_with_message!(
+                Errno::ENOTTY,
+                "the ioctl command is not supported by loop devices"
+            ),
+        });
+
+        Ok(0)
+    }
+
+    /// Binds the loop device to the file of `fd`.
+    fn bind(&self, fd: FileDesc, block_size: u32, info: Option<&LoopInfo64>) -> Result<()> {
+        let file = {
+            let task = Task::current().unwrap();
+            let thread_local = task.as_thread_local().unwrap();
+            let file_table = thread_local.borrow_file_table();
+            let file_table_locked = file_table.unwrap().read();
+            file_table_locked.get_file(fd)?.clone()
+        };
+        if file.path().type_() != InodeType::File {
+            return_errno_with_message!(Errno::EINVAL, "the backing file is not a regular file");
+        }
+
+        let block_size = if block_size == 0 {
+            SECTOR_SIZE as u32
+        } else {
+            check_block_size(block_size as u64)?
+        };
+
+        let mut backing = Backing::new(file, block_size);
+        if let Some(info) = info {
+            let flags = LoopFlags::from_bits(info.lo_flags).ok_or_else(|| {
+                Error::with_message(Errno::EINVAL, "the loop flags are not valid")
+            })?;
+            backing.set_info(info)?;
+            backing.flags = flags;
+        }
+        if !backing.file.access_mode().is_writable() {
+            backing.flags.insert(LoopFlags::READ_ONLY);
+        }
+        if backing.flags.contains(LoopFlags::AUTOCLEAR) {
+            // TODO: Detach the backing file when the loop device is closed for the last time.
+            warn!("the autoclear flag of loop devices is not supported");
+        }
+        backing.check_direct_io()?;
+        backing.update_nr_sectors();
+
+        let should_scan_partitions = backing.flags.contains(LoopFlags::PARTSCAN);
+        {
+            let mut backing_guard = self.backing.lock();
+            if backing_guard.is_some() {
+                return_errno_with_message!(Errno::EBUSY, "the loop device is already bound");
+            }
+            *backing_guard = Some(Arc::new(backing));
+        }
+
+        if should_scan_partitions {
+            self.rescan_partitions();
+        }
+
+        Ok(())
+    }
+
+    /// Unbinds the loop device from its backing file.
+    fn unbind(&self) -> Result<()> {
+        if self.backing.lock().take().is_none() {
+            return_errno_with_message!(Errno::ENXIO, "the loop device is not bound to a file");
+        }
+
+        self.rescan_partitions();
+
+        Ok(())
+    }
+
+    fn set_status(&self, info: &LoopInfo64) -> Result<()> {
+        let new_flags = LoopFlags::from_bits_truncate(info.lo_flags);
+        let mut should_scan_partitions = false;
+
+        self.update_backing(|backing| {
+            backing.set_info(info)?;
+
+            // Only some flags can be changed after binding.
+            // Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/loop.h#L28>.
+            let settable = LoopFlags::AUTOCLEAR | LoopFlags::PARTSCAN;
+            let clearable = LoopFlags::AUTOCLEAR;
+            should_scan_partitions = !backing.flags.contains(LoopFlags::PARTSCAN)
+                && new_flags.contains(LoopFlags::PARTSCAN);
+            backing.flags |= new_flags & settable;
+            backing.flags &= !(clearable - new_flags);
+
+            Ok(())
+        })?;
+
+        if should_scan_partitions {
+            self.rescan_partitions();
+        }
+
+        Ok(())
+    }
+
+    /// Updates the configurations of the backing file and recalculates the device size.
+    fn update_backing<F>(&self, update_fn: F) -> Result<()>
+    where
+        F: FnOnce(&mut Backing) -> Result<()>,
+    {
+        let mut backing_guard = self.backing.lock();
+        let Some(old_backing) = backing_guard.as_ref() else {
+            return_errno_with_message!(Errno::ENXIO, "the loop device is not bound to a file");
+        };
+
+        let mut new_backing = Backing::clone(old_backing);
+        update_fn(&mut new_backing)?;
+        new_backing.update_nr_sectors();
+        *backing_guard = Some(Arc::new(new_backing));
+
+        Ok(())
+    }
+
+    /// Re-reads the partition table in the backing file.
+    ///
+    /// If the loop device is unbound or partition scanning is disabled, all partitions will be
+    /// removed.
+    fn rescan_partitions(&self) {
+        let device = self.weak_self.upgrade().unwrap() as Arc<dyn BlockDevice>;
+        block::rescan_partitions(&device);
+    }
+}
+
+impl Debug for LoopDevice {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("LoopDevice")
+            .field("id", &self.id)
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl BlockDevice for LoopDevice {
+    fn enqueue(&self, bio: SubmittedBio) -> core::result::Result<(), BioEnqueueError> {
+        let status = match self.backing() {
+            Some(backing) => backing.handle_bio(&bio),
+            None => BioStatus::IoError,
+        };
+        bio.complete(status);
+
+        Ok(())
+    }
+
+    fn metadata(&self) -> BlockDeviceMeta {
+        BlockDeviceMeta {
+            max_nr_segments_per_bio: usize::MAX,
+            nr_sectors: self.backing().map_or(0, |backing| backing.nr_sectors),
+            supports_fua: false,
+            // Discarding is implemented by punching holes in the backing file.
+            max_discard_sectors: usize::MAX,
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn id(&self) -> DeviceId {
+        self.id
+    }
+
+    fn can_scan_partitions(&self) -> bool {
+        self.backing()
+            .is_some_and(|backing| backing.flags.contains(LoopFlags::PARTSCAN))
+    }
+
+    fn set_partitions(&self, infos: Vec<Option<PartitionInfo>>) {
+        let mut partitions = self.partitions.lock();
+        let id_allocator = EXTENDED_DEVICE_ID_ALLOCATOR.get().unwrap();
+
+        for partition in partitions.drain(..) {
+            let _ = aster_block::unregister(partition.id());
+            id_allocator.release(partition.id());
+        }
+
+        let device = self.weak_self.upgrade().unwrap() as Arc<dyn BlockDevice>;
+        for (index, info_opt) in infos.iter().enumerate() {
+            let Some(info) = info_opt else {
+                continue;
+            };
+
+            // Like Linux with the default `max_part`, partitions of loop devices always use
+            // extended device IDs.
+            let id = id_allocator.allocate();
+            let number = index as u32 + 1;
+            let name = partition_name(&self.name, number);
+            let partition = Arc::new(PartitionNode::new(id, name, device.clone(), number, *info));
+            if aster_block::register(partition.clone()).is_err() {
+                id_allocator.release(id);
+                continue;
+            }
+
+            partitions.push(partition);
+        }
+    }
+
+    fn partitions(&self) -> Option<Vec<Arc<dyn BlockDevice>>> {
+        let partitions = self.partitions.lock();
+        let devices = partitions
+            .iter()
+            .map(|partition| partition.clone() as Arc<dyn BlockDevice>)
+            .collect();
+        Some(devices)
+    }
+}
+
+/// The backing file of a loop device and its configurations.
+#[derive(Clone)]
+struct Backing {
+    file: Arc<dyn FileLike>,
+    /// The offset in bytes where the device starts in the file.
+    offset: usize,
+    /// The maximum size in bytes of the device, or zero if there is no limit.
+    size_limit: usize,
+    /// The number of sectors of the device.
+    nr_sectors: usize,
+    flags: LoopFlags,
+    block_size: u32,
+    file_name: [u8; LO_NAME_SIZE],
+}
+
+impl Backing {
+    fn new(file: Arc<dyn FileLike>, block_size: u32) -> Self {
+        Self {
+            file,
+            offset: 0,
+            size_limit: 0,
+            nr_sectors: 0,
+            flags: LoopFlags::empty(),
+            block_size,
+            file_name: [0; LO_NAME_SIZE],
+        }
+    }
+
+    fn set_info(&mut self, info: &LoopInfo64) -> Result<()> {
+        if info.lo_encrypt_type != 0 || info.lo_encrypt_key_size != 0 {
+            return_errno_with_message!(Errno::EINVAL, "loop encryption is not supported");
+        }
+        if info.lo_offset > i64::MAX as u64 || info.lo_sizelimit > i64::MAX as u64 {
+            return_errno_with_message!(Errno::EOVERFLOW, "the offset or size limit is too large");
+        }
+
+        self.offset = info.lo_offset as usize;
+        self.size_limit = info.lo_sizelimit as usize;
+        self.file_name = info.lo_file_name;
+        self.file_name[LO_NAME_SIZE - 1] = 0;
+
+        Ok(())
+    }
+
+    fn info(&self, index: u32) -> LoopInfo64 {
+        let metadata = self.file.path().inode().metadata();
+
+        LoopInfo64 {
+            lo_device: metadata.container_dev_id.as_encoded_u64(),
+            lo_inode: metadata.ino,
+            lo_rdevice: metadata.self_dev_id.map_or(0, |id| id.as_encoded_u64()),
+            lo_offset: self.offset as u64,
+            lo_sizelimit: self.size_limit as u64,
+            lo_number: index,
+            lo_flags: self.flags.bits(),
+            lo_file_name: self.file_name,
+            ..LoopInfo64::new_zeroed()
+        }
+    }
+
+    fn check_direct_io(&self) -> Result<()> {
+        if self.flags.contains(LoopFlags::DIRECT_IO)
+            && !self.offset.is_multiple_of(self.block_size as usize)
+        {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "the offset is not aligned to the block size for direct I/O"
+            );
+        }
+
+        Ok(())
+    }
+
+    fn update_nr_sectors(&mut self) {
+        let file_size = self.file.path().size();
+
+        let mut size = file_size.saturating_sub(self.offset);
+        if self.size_limit != 0 {
+            size = size.min(self.size_limit);
+        }
+        self.nr_sectors = size / SECTOR_SIZE;
+    }
+
+    fn handle_bio(&self, bio: &SubmittedBio) -> BioStatus {
+        let type_ = bio.type_();
+        match type_ {
+            BioType::Read | BioType::Write | BioType::Discard => (),
+            BioType::Flush => {
+                return match self.file.path().inode().sync_data() {
+                    Ok(()) => BioStatus::Complete,
+                    Err(_) => BioStatus::IoError,
+                };
+            }
+        }
+
+        if type_ != BioType::Read && self.flags.contains(LoopFlags::READ_ONLY) {
+            return BioStatus::IoError;
+        }
+
+        let start_sid = bio.sid_range().start.to_raw() + bio.sid_offset();
+        let end_sid = bio.sid_range().end.to_raw() + bio.sid_offset();
+        if end_sid > self.nr_sectors as u64 {
+            return BioStatus::IoError;
+        }
+
+        let mut pos = self.offset + start_sid as usize * SECTOR_SIZE;
+        if type_ == BioType::Discard {
+            let len = (end_sid - start_sid) as usize * SECTOR_SIZE;
+            return self.punch_hole(pos, len);
+        }
+        for segment in bio.segments() {
+            let res = if type_ == BioType::Read {
+                self.read_segment(pos, segment)
+            } else {
+                self.write_segment(pos, segment)
+            };
+            if res.is_err() {
+                return BioStatus::IoError;
+            }
+            pos += segment.nbytes();
+        }
+
+        BioStatus::Complete
+    }
+
+    /// Discards the range of the backing file by punching a hole in it.
+    fn punch_hole(&self, pos: usize, len: usize) -> BioStatus {
+        let inode = self.file.path().inode();
+        match inode.fallocate(FallocMode::PunchHoleKeepSize, pos, len) {
+            Ok(()) => BioStatus::Complete,
+            Err(err) if err.error() == Errno::EOPNOTSUPP => BioStatus::NotSupported,
+            Err(_) => BioStatus::IoError,
+        }
+    }
+
+    fn read_segment(&self, mut pos: usize, segment: &BioSegment) -> Result<()> {
+        let inode = self.file.path().inode();
+        let status_flags = self.status_flags(pos, segment.nbytes());
+
+        let mut writer = segment.inner_dma_slice().writer()?.to_fallible();
+        while writer.avail() > 0 {
+            let read_len = inode.read_at(pos, &mut writer, status_flags)?;
+            if read_len == 0 {
+                break;
+            }
+            pos += read_len;
+        }
+
+        // The device may end in the middle of a sector beyond the end of the file.
+        writer.fill_zeros(writer.avail()).map_err(|(err, _)| err)?;
+
+        Ok(())
+    }
+
+    fn write_segment(&self, mut pos: usize, segment: &BioSegment) -> Result<()> {
+        let inode = self.file.path().inode();
+        let status_flags = self.status_flags(pos, segment.nbytes());
+
+        let mut reader = segment.inner_dma_slice().reader()?.to_fallible();
+        while reader.remain() > 0 {
+            let write_len = inode.write_at(pos, &mut reader, status_flags)?;
+            if write_len == 0 {
+                return_errno_with_message!(Errno::EIO, "the backing file cannot be written");
+            }
+            pos += write_len;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the status flags for the I/O on the backing file.
+    ///
+    /// Direct I/O is used only if the I/O is aligned for the backing file. Otherwise, the I/O
+    /// falls back to buffered I/O.
+    fn status_flags(&self, pos: usize, len: usize) -> StatusFlags {
+        if !self.flags.contains(LoopFlags::DIRECT_IO) {
+            return StatusFlags::empty();
+        }
+
+        let align = self.file.path().inode().metadata().optimal_block_size;
+        if pos.is_multiple_of(align) && len.is_multiple_of(align) {
+            StatusFlags::O_DIRECT
+        } else {
+            StatusFlags::empty()
+        }
+    }
+}
+
+fn check_block_size(block_size: u64) -> Result<u32> {
+    if !(SECTOR_SIZE as u64..=PAGE_SIZE as u64).contains(&block_size)
+        || !block_size.is_power_of_two()
+    {
+        return_errno_with_message!(Errno::EINVAL, "the block size is not valid");
+    }
+
+    Ok(block_size as u32)
+}
+
+bitflags! {
+    /// The flags of a loop device.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/loop.h#L19>.
+    struct LoopFlags: u32 {
+        const READ_ONLY = 1;
+        const AUTOCLEAR = 4;
+        const PARTSCAN  = 8;
+        const DIRECT_IO = 16;
+    }
+}
+
+/// The status of a loop device; `struct loop_info64` in Linux.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/loop.h#L54>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct LoopInfo64 {
+    lo_device: u64,
+    lo_inode: u64,
+    lo_rdevice: u64,
+    lo_offset: u64,
+    lo_sizelimit: u64,
+    lo_number: u32,
+    lo_encrypt_type: u32,
+    lo_encrypt_key_size: u32,
+    lo_flags: u32,
+    lo_file_name: [u8; LO_NAME_SIZE],
+    lo_crypt_name: [u8; LO_NAME_SIZE],
+    lo_encrypt_key: [u8; LO_KEY_SIZE],
+    lo_init: [u64; 2],
+}
+
+/// The configuration of a loop device; `struct loop_config` in Linux.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/loop.h#L79>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct LoopConfig {
+    fd: u32,
+    block_size: u32,
+    info: LoopInfo64,
+    reserved: [u64; 8],
+}
+
+mod ioctl_defs {
+    use super::{LoopConfig, LoopInfo64};
+    use crate::util::ioctl::{InData, NoData, OutData, PassByVal, ioc};
+
+    // Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/loop.h#L110>
+
+    pub(super) type SetFd        = ioc!(LOOP_SET_FD,         0x4C00, InData<i32, PassByVal>);
+    pub(super) type ClrFd        = ioc!(LOOP_CLR_FD,         0x4C01, NoData);
+    pub(super) type SetStatus64  = ioc!(LOOP_SET_STATUS64,   0x4C04, InData<LoopInfo64>);
+    pub(super) type GetStatus64  = ioc!(LOOP_GET_STATUS64,   0x4C05, OutData<LoopInfo64>);
+    pub(super) type SetCapacity  = ioc!(LOOP_SET_CAPACITY,   0x4C07, NoData);
+    pub(super) type SetDirectIo  = ioc!(LOOP_SET_DIRECT_IO,  0x4C08, InData<u64, PassByVal>);
+    pub(super) type SetBlockSize = ioc!(LOOP_SET_BLOCK_SIZE, 0x4C09, InData<u64, PassByVal>);
+    pub(super) type Configure    = ioc!(LOOP_CONFIGURE,      0x4C0A, InData<LoopConfig>);
+}
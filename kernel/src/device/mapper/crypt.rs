@@ -0,0 +1,267 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The crypt target, which transparently encrypts the data on an underlying device.
+//!
+//! The parameters are `<cipher> <key> <iv_offset> <device> <offset> [<#opt_params> <opt_params>]`.
+//! The cipher is specified either as `<cipher>-<chainmode>-<ivmode>` (e.g., `aes-xts-plain64`,
+//! which is the default of LUKS2) or as `capi:<cipher_api_spec>-<ivmode>` (e.g.,
+//! `capi:xts(aes)-plain64`), and it is allocated from the crypto API. The key must be given in
+//! hexadecimal.
+//!
+//! Reference: <https://docs.kernel.org/admin-guide/device-mapper/dm-crypt.html>
+
+use aster_block::SECTOR_SIZE;
+use device_id::DeviceId;
+
+use super::table::{DeviceRange, Target, TargetType, parse_u64};
+use crate::{
+    prelude::*,
+    util::crypto::api::{Skcipher, SkcipherAlg},
+};
+
+pub(super) const TARGET_TYPE: TargetType = TargetType {
+    name: "crypt",
+    version: [1, 27, 0],
+    new: CryptTarget::new,
+};
+
+/// The optional parameters that can be accepted.
+///
+/// They only tune the performance or are not applicable, so they are simply ignored.
+const IGNORED_OPT_PARAMS: &[&str] = &[
+    "allow_discards",
+    "same_cpu_crypt",
+    "submit_from_crypt_cpus",
+    "no_read_workqueue",
+    "no_write_workqueue",
+    "sector_size:512",
+];
+
+struct CryptTarget {
+    cipher_name: String,
+    key: Vec<u8>,
+    cipher: Box<dyn Skcipher>,
+    iv_size: usize,
+    iv_mode: IvMode,
+    iv_offset: u64,
+    range: DeviceRange,
+    opt_params: Vec<String>,
+}
+
+impl CryptTarget {
+    fn new(params: &str, len: u64) -> Result<Box<dyn Target>> {
+        let params = params.split_ascii_whitespace().collect::<Vec<_>>();
+        let [cipher_name, key, iv_offset, device, offset, ref opt_params @ ..] = params[..] else {
+            return_errno_with_message!(Errno::EINVAL, "the number of parameters is invalid");
+        };
+
+        let (alg, iv_mode) = parse_cipher(cipher_name)?;
+        let key = parse_key(key)?;
+        let cipher = alg.new_cipher(&key)?;
+        let iv_offset = parse_u64(iv_offset)?;
+        let opt_params = parse_opt_params(opt_params)?;
+
+        let range = DeviceRange::open(device, parse_u64(offset)?, len)?;
+
+        Ok(Box::new(Self {
+            cipher_name: cipher_name.to_string(),
+            key,
+            cipher,
+            iv_size: alg.iv_size(),
+            iv_mode,
+            iv_offset,
+            range,
+            opt_params,
+        }))
+    }
+
+    /// Returns the IV of the sector.
+    fn iv(&self, sector: u64) -> Vec<u8> {
+        let mut iv = vec![0u8; self.iv_size];
+        self.iv_mode.generate(self.iv_offset + sector, &mut iv);
+        iv
+    }
+}
+
+impl Target for CryptTarget {
+    fn read(&self, sector: u64, buf: &mut [u8]) -> Result<()> {
+        self.range.read(sector, buf)?;
+
+        for (i, chunk) in buf.chunks_exact_mut(SECTOR_SIZE).enumerate() {
+            self.cipher
+                .decrypt(&mut self.iv(sector + i as u64), chunk)?;
+        }
+
+        Ok(())
+    }
+
+    fn write(&self, sector: u64, buf: &[u8]) -> Result<()> {
+        let mut encrypted = buf.to_vec();
+
+        for (i, chunk) in encrypted.chunks_exact_mut(SECTOR_SIZE).enumerate() {
+            self.cipher
+                .encrypt(&mut self.iv(sector + i as u64), chunk)?;
+        }
+
+        self.range.write(sector, &encrypted)
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.range.flush()
+    }
+
+    fn params(&self) -> String {
+        let key = self
+            .key
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+        let id = self.range.device_id();
+
+        let mut params = format!(
+            "{} {} {} {}:{} {}",
+            self.cipher_name,
+            key,
+            self.iv_offset,
+            id.major().get(),
+            id.minor().get(),
+            self.range.offset()
+        );
+        if !self.opt_params.is_empty() {
+            params.push_str(&format!(
+                " {} {}",
+                self.opt_params.len(),
+                self.opt_params.join(" ")
+            ));
+        }
+
+        params
+    }
+
+    fn deps(&self) -> Vec<DeviceId> {
+        vec![self.range.device_id()]
+    }
+}
+
+impl Drop for CryptTarget {
+    fn drop(&mut self) {
+        self.key.fill(0);
+    }
+}
+
+/// The IV modes, which generate the IVs from the sector numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IvMode {
+    /// The cipher takes no IVs.
+    None,
+    /// The IV is the little-endian 32-bit sector number (`plain`).
+    Plain,
+    /// The IV is the little-endian 64-bit sector number (`plain64`).
+    Plain64,
+    /// The IV is the big-endian 64-bit sector number (`plain64be`).
+    Plain64Be,
+    /// The IV is always zero (`null`).
+    Null,
+}
+
+impl IvMode {
+    fn from_name(name: &str) -> Result<Self> {
+        let iv_mode = match name {
+            "plain" => Self::Plain,
+            "plain64" => Self::Plain64,
+            "plain64be" => Self::Plain64Be,
+            "null" => Self::Null,
+            // TODO: Support other IV modes (e.g., `essiv`), which require hash algorithms or
+            // options.
+            _ => return_errno_with_message!(Errno::EINVAL, "the IV mode is not supported"),
+        };
+        Ok(iv_mode)
+    }
+
+    /// Generates the IV of the sector, which is padded with zeros.
+    fn generate(self, sector: u64, iv: &mut [u8]) {
+        let sector_bytes = match self {
+            Self::None | Self::Null => return,
+            Self::Plain => &(sector as u32).to_le_bytes()[..],
+            Self::Plain64 => &sector.to_le_bytes()[..],
+            Self::Plain64Be => &sector.to_be_bytes()[..],
+        };
+        let len = sector_bytes.len().min(iv.len());
+        match self {
+            // The big-endian number is placed at the end of the IV.
+            Self::Plain64Be => iv[iv.len() - len..].copy_from_slice(&sector_bytes[8 - len..]),
+            _ => iv[..len].copy_from_slice(&sector_bytes[..len]),
+        }
+    }
+}
+
+/// Parses the cipher specification into the algorithm and the IV mode.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/md/dm-crypt.c#L2877>.
+fn parse_cipher(cipher: &str) -> Result<(SkcipherAlg, IvMode)> {
+    let (api_name, iv_mode) = if let Some(spec) = cipher.strip_prefix("capi:") {
+        match spec.rsplit_once('-') {
+            Some((api_name, iv_mode)) => (api_name.to_string(), Some(iv_mode)),
+            None => (spec.to_string(), None),
+        }
+    } else {
+        let mut parts = cipher.splitn(3, '-');
+        let cipher = parts.next().unwrap();
+        // Like Linux, the chaining mode and the IV mode default to `cbc` and `plain`.
+        let (chain_mode, iv_mode) = match (parts.next(), parts.next()) {
+            (None, _) | (Some("plain"), None) => ("cbc", Some("plain")),
+            (Some(chain_mode), iv_mode) => (chain_mode, iv_mode),
+        };
+        (format!("{}({})", chain_mode, cipher), iv_mode)
+    };
+
+    let alg = SkcipherAlg::from_name(&api_name)
+        .map_err(|_| Error::with_message(Errno::EINVAL, "the cipher is not supported"))?;
+    let iv_mode = match iv_mode {
+        Some(iv_mode) => IvMode::from_name(iv_mode)?,
+        None if alg.iv_size() == 0 => IvMode::None,
+        None => return_errno_with_message!(Errno::EINVAL, "the IV mode is missing"),
+    };
+
+    Ok((alg, iv_mode))
+}
+
+fn parse_key(key: &str) -> Result<Vec<u8>> {
+    // TODO: Support the keys in the kernel keyring, which are specified as
+    // `:<key_size>:<key_type>:<key_description>`.
+    if key.is_empty() || key.len() % 2 != 0 {
+        return_errno_with_message!(Errno::EINVAL, "the key size is invalid");
+    }
+    if !key.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return_errno_with_message!(Errno::EINVAL, "the key is not hexadecimal");
+    }
+
+    let bytes = (0..key.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&key[i..i + 2], 16).unwrap())
+        .collect();
+
+    Ok(bytes)
+}
+
+fn parse_opt_params(opt_params: &[&str]) -> Result<Vec<String>> {
+    let Some((count, opt_params)) = opt_params.split_first() else {
+        return Ok(Vec::new());
+    };
+
+    if parse_u64(count)? != opt_params.len() as u64 {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "the number of optional parameters is invalid"
+        );
+    }
+    if let Some(opt_param) = opt_params
+        .iter()
+        .find(|opt_param| !IGNORED_OPT_PARAMS.contains(opt_param))
+    {
+        warn!("the optional parameter '{}' is not supported", opt_param);
+        return_errno_with_message!(Errno::EINVAL, "the optional parameter is not supported");
+    }
+
+    Ok(opt_params.iter().map(|opt_param| opt_param.to_string()).collect())
+}
@@ -0,0 +1,600 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The ioctls of the device mapper.
+//!
+//! Every ioctl takes a `struct dm_ioctl` header, which may be followed by the input data starting
+//! at `data_start`. The output data is written right after the header.
+//!
+//! Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/md/dm-ioctl.c>
+
+use core::ffi::CStr;
+
+use device_id::DeviceId;
+
+use super::{
+    DEVICES, MappedDevice, create_device, remove_device,
+    table::{TARGET_TYPES, Table, TargetSpec},
+};
+use crate::{
+    current_userspace,
+    prelude::*,
+    process::{credentials::capabilities::CapSet, posix_thread::AsPosixThread},
+    util::ioctl::{RawIoctl, dispatch_ioctl},
+};
+
+/// The version of the ioctl interface.
+const DM_VERSION: [u32; 3] = [4, 48, 0];
+
+const DM_NAME_LEN: usize = 128;
+const DM_UUID_LEN: usize = 129;
+const DM_MAX_TYPE_NAME: usize = 16;
+
+const DM_READONLY_FLAG: u32 = 1 << 0;
+const DM_SUSPEND_FLAG: u32 = 1 << 1;
+const DM_PERSISTENT_DEV_FLAG: u32 = 1 << 3;
+const DM_STATUS_TABLE_FLAG: u32 = 1 << 4;
+const DM_ACTIVE_PRESENT_FLAG: u32 = 1 << 5;
+const DM_INACTIVE_PRESENT_FLAG: u32 = 1 << 6;
+const DM_BUFFER_FULL_FLAG: u32 = 1 << 8;
+const DM_QUERY_INACTIVE_TABLE_FLAG: u32 = 1 << 12;
+const DM_UUID_FLAG: u32 = 1 << 14;
+
+const DM_NAME_LIST_FLAG_HAS_UUID: u32 = 1;
+const DM_NAME_LIST_FLAG_DOESNT_HAVE_UUID: u32 = 2;
+
+/// The alignment of the entries in the input and output data.
+const DATA_ALIGN: usize = 8;
+
+/// Handles an ioctl command, which fills the output data if there is any.
+type Handler = fn(&mut DmIoctl, &[u8]) -> Result<Option<Vec<u8>>>;
+
+/// Handles the ioctls on `/dev/mapper/control`.
+pub(in crate::device) fn ioctl(raw_ioctl: RawIoctl) -> Result<i32> {
+    use ioctl_defs::*;
+
+    let handler: Handler = dispatch_ioctl!(match raw_ioctl {
+        Version => {
+            version
+        }
+        RemoveAll => {
+            remove_all
+        }
+        ListDevices => {
+            list_devices
+        }
+        DevCreate => {
+            dev_create
+        }
+        DevRemove => {
+            dev_remove
+        }
+        DevRename => {
+            dev_rename
+        }
+        DevSuspend => {
+            dev_suspend
+        }
+        DevStatus => {
+            dev_status
+        }
+        TableLoad => {
+            table_load
+        }
+        TableClear => {
+            table_clear
+        }
+        TableDeps => {
+            table_deps
+        }
+        TableStatus => {
+            table_status
+        }
+        ListVersions => {
+            list_versions
+        }
+        GetTargetVersion => {
+            get_target_version
+        }
+        _ => return_errno_with_message!(Errno::ENOTTY, "the ioctl command is unknown"),
+    });
+
+    let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+    if !credentials.effective_capset().contains(CapSet::SYS_ADMIN) {
+        return_errno_with_message!(Errno::EACCES, "the device mapper requires CAP_SYS_ADMIN");
+    }
+
+    let arg = raw_ioctl.arg();
+    let user_space = current_userspace!();
+
+    let mut header: DmIoctl = user_space.read_val(arg)?;
+    let user_version = header.version;
+    header.version = DM_VERSION;
+    if user_version[0] != DM_VERSION[0] || user_version[1] > DM_VERSION[1] {
+        user_space.write_val(arg, &header.version)?;
+        return_errno_with_message!(Errno::EINVAL, "the ioctl interface version is incompatible");
+    }
+
+    let data_size = header.data_size as usize;
+    let data_start = header.data_start as usize;
+    if data_size < size_of::<DmIoctl>() || data_start > data_size {
+        return_errno_with_message!(Errno::EINVAL, "the data size is invalid");
+    }
+    let mut input = vec![0u8; data_size - data_start];
+    user_space.read_bytes(arg + data_start, &mut input)?;
+
+    header.flags &= !(DM_BUFFER_FULL_FLAG | DM_ACTIVE_PRESENT_FLAG | DM_INACTIVE_PRESENT_FLAG);
+
+    if let Some(output) = handler(&mut header, &input)? {
+        header.data_start = size_of::<DmIoctl>() as u32;
+        if output.len() > data_size - size_of::<DmIoctl>() {
+            header.flags |= DM_BUFFER_FULL_FLAG;
+        } else {
+            user_space.write_bytes(arg + size_of::<DmIoctl>(), &output)?;
+            header.data_size = (size_of::<DmIoctl>() + output.len()) as u32;
+        }
+    }
+
+    user_space.write_val(arg, &header)?;
+    Ok(0)
+}
+
+fn version(_header: &mut DmIoctl, _input: &[u8]) -> Result<Option<Vec<u8>>> {
+    Ok(None)
+}
+
+fn remove_all(_header: &mut DmIoctl, _input: &[u8]) -> Result<Option<Vec<u8>>> {
+    let devices = DEVICES.lock().values().cloned().collect::<Vec<_>>();
+    for device in devices {
+        remove_device(&device)?;
+    }
+
+    Ok(None)
+}
+
+fn list_devices(_header: &mut DmIoctl, _input: &[u8]) -> Result<Option<Vec<u8>>> {
+    let devices = DEVICES.lock().values().cloned().collect::<Vec<_>>();
+
+    // Each entry is `struct dm_name_list`, which is followed by the event number, the flags,
+    // and the UUID.
+    let mut output = Vec::new();
+    let mut last_entry = None;
+    for device in devices {
+        let entry = output.len();
+        if let Some(last_entry) = last_entry {
+            write_u32(&mut output, last_entry + 8, (entry - last_entry) as u32);
+        }
+        last_entry = Some(entry);
+
+        let state = device.state.lock();
+        output.extend_from_slice(&device.id.as_encoded_u64().to_ne_bytes());
+        output.extend_from_slice(&0u32.to_ne_bytes());
+        push_cstr(&mut output, &state.name);
+        align_data(&mut output);
+
+        output.extend_from_slice(&state.event_nr.to_ne_bytes());
+        let flags = if state.uuid.is_empty() {
+            DM_NAME_LIST_FLAG_DOESNT_HAVE_UUID
+        } else {
+            DM_NAME_LIST_FLAG_HAS_UUID
+        };
+        output.extend_from_slice(&flags.to_ne_bytes());
+        push_cstr(&mut output, &state.uuid);
+        align_data(&mut output);
+    }
+
+    // An empty entry indicates that there are no devices.
+    if output.is_empty() {
+        output.resize(16, 0);
+    }
+
+    Ok(Some(output))
+}
+
+fn dev_create(header: &mut DmIoctl, _input: &[u8]) -> Result<Option<Vec<u8>>> {
+    let name = read_cstr(&header.name)?;
+    if name.is_empty() || name.contains('/') || name == "." || name == ".." {
+        return_errno_with_message!(Errno::EINVAL, "the device name is invalid");
+    }
+    let uuid = read_cstr(&header.uuid)?;
+
+    let minor = if header.flags & DM_PERSISTENT_DEV_FLAG != 0 {
+        let (_, minor) = device_id::decode_device_numbers(header.dev);
+        Some(minor)
+    } else {
+        None
+    };
+
+    let device = create_device(name, uuid, minor)?;
+    fill_status(header, &device);
+
+    Ok(None)
+}
+
+fn dev_remove(header: &mut DmIoctl, _input: &[u8]) -> Result<Option<Vec<u8>>> {
+    let device = find_device(header)?;
+    remove_device(&device)?;
+
+    Ok(None)
+}
+
+fn dev_rename(header: &mut DmIoctl, input: &[u8]) -> Result<Option<Vec<u8>>> {
+    let device = find_device(header)?;
+    let is_uuid = header.flags & DM_UUID_FLAG != 0;
+
+    let new_value = read_cstr(input)?;
+    let max_len = if is_uuid { DM_UUID_LEN } else { DM_NAME_LEN };
+    if new_value.is_empty() || new_value.len() >= max_len {
+        return_errno_with_message!(Errno::EINVAL, "the new name or UUID is invalid");
+    }
+
+    let devices = DEVICES.lock();
+    if devices.values().any(|other| {
+        let state = other.state.lock();
+        if is_uuid {
+            state.uuid == new_value
+        } else {
+            state.name == new_value
+        }
+    }) {
+        return_errno_with_message!(Errno::EBUSY, "the new name or UUID is already in use");
+    }
+
+    let mut state = device.state.lock();
+    if is_uuid {
+        if !state.uuid.is_empty() {
+            return_errno_with_message!(Errno::EINVAL, "the UUID of a device cannot be changed");
+        }
+        state.uuid = new_value;
+    } else {
+        state.name = new_value;
+    }
+    drop(state);
+    drop(devices);
+
+    fill_status(header, &device);
+    Ok(None)
+}
+
+fn dev_suspend(header: &mut DmIoctl, _input: &[u8]) -> Result<Option<Vec<u8>>> {
+    let device = find_device(header)?;
+
+    if header.flags & DM_SUSPEND_FLAG != 0 {
+        device.suspend();
+    } else {
+        device.resume();
+    }
+
+    fill_status(header, &device);
+    Ok(None)
+}
+
+fn dev_status(header: &mut DmIoctl, _input: &[u8]) -> Result<Option<Vec<u8>>> {
+    let device = find_device(header)?;
+    fill_status(header, &device);
+
+    Ok(None)
+}
+
+fn table_load(header: &mut DmIoctl, input: &[u8]) -> Result<Option<Vec<u8>>> {
+    let device = find_device(header)?;
+
+    if header.target_count == 0 {
+        return_errno_with_message!(Errno::EINVAL, "no targets are specified");
+    }
+
+    // Each target is specified by `struct dm_target_spec`, which is followed by the parameters.
+    // The offset of the next target is relative to the current one.
+    let mut specs = Vec::new();
+    let mut offset = 0;
+    for i in 0..header.target_count {
+        if i > 0 {
+            let next = read_spec(input, offset)?.next as usize;
+            offset = offset
+                .checked_add(next)
+                .filter(|_| next >= size_of::<DmTargetSpec>())
+                .ok_or_else(|| Error::with_message(Errno::EINVAL, "the target is invalid"))?;
+        }
+
+        let spec = read_spec(input, offset)?;
+        specs.push(TargetSpec {
+            start: spec.sector_start,
+            len: spec.length,
+            type_name: read_cstr(&spec.target_type)?,
+            params: read_cstr(&input[offset + size_of::<DmTargetSpec>()..])?,
+        });
+    }
+
+    let table = Table::new(specs, header.flags & DM_READONLY_FLAG != 0)?;
+    if table.deps().contains(&device.id) {
+        return_errno_with_message!(Errno::EINVAL, "the table cannot map the device to itself");
+    }
+
+    device.state.lock().inactive_table = Some(Arc::new(table));
+
+    fill_status(header, &device);
+    Ok(None)
+}
+
+fn table_clear(header: &mut DmIoctl, _input: &[u8]) -> Result<Option<Vec<u8>>> {
+    let device = find_device(header)?;
+    device.state.lock().inactive_table = None;
+
+    fill_status(header, &device);
+    Ok(None)
+}
+
+fn table_deps(header: &mut DmIoctl, _input: &[u8]) -> Result<Option<Vec<u8>>> {
+    let device = find_device(header)?;
+    fill_status(header, &device);
+
+    let deps = selected_table(header, &device).map_or_else(Vec::new, |table| table.deps());
+
+    // The output is `struct dm_target_deps`.
+    let mut output = Vec::new();
+    output.extend_from_slice(&(deps.len() as u32).to_ne_bytes());
+    output.extend_from_slice(&0u32.to_ne_bytes());
+    for dep in deps {
+        output.extend_from_slice(&dep.as_encoded_u64().to_ne_bytes());
+    }
+
+    Ok(Some(output))
+}
+
+fn table_status(header: &mut DmIoctl, _input: &[u8]) -> Result<Option<Vec<u8>>> {
+    let device = find_device(header)?;
+    fill_status(header, &device);
+
+    let Some(table) = selected_table(header, &device) else {
+        return Ok(Some(Vec::new()));
+    };
+    let is_table = header.flags & DM_STATUS_TABLE_FLAG != 0;
+
+    // Each target is described by `struct dm_target_spec`, which is followed by the status.
+    // The offset of the next target is relative to the start of the output data.
+    let mut output = Vec::new();
+    for entry in table.targets() {
+        let spec_offset = output.len();
+        output.resize(spec_offset + size_of::<DmTargetSpec>(), 0);
+
+        // TODO: Report the runtime information of the targets. Neither the linear target nor
+        // the crypt target has any information to report for now.
+        let status = if is_table {
+            entry.target.params()
+        } else {
+            String::new()
+        };
+        push_cstr(&mut output, &status);
+        align_data(&mut output);
+
+        let mut spec = DmTargetSpec {
+            sector_start: entry.start,
+            length: entry.len,
+            status: 0,
+            next: output.len() as u32,
+            target_type: [0; DM_MAX_TYPE_NAME],
+        };
+        write_cstr(&mut spec.target_type, entry.type_.name);
+        output[spec_offset..spec_offset + size_of::<DmTargetSpec>()]
+            .copy_from_slice(spec.as_bytes());
+    }
+
+    Ok(Some(output))
+}
+
+fn list_versions(_header: &mut DmIoctl, _input: &[u8]) -> Result<Option<Vec<u8>>> {
+    let mut output = Vec::new();
+    let mut last_entry = None;
+    for target_type in TARGET_TYPES {
+        let entry = output.len();
+        if let Some(last_entry) = last_entry {
+            write_u32(&mut output, last_entry, (entry - last_entry) as u32);
+        }
+        last_entry = Some(entry);
+
+        push_target_version(&mut output, target_type.name, target_type.version);
+    }
+
+    Ok(Some(output))
+}
+
+fn get_target_version(_header: &mut DmIoctl, input: &[u8]) -> Result<Option<Vec<u8>>> {
+    // The input is `struct dm_target_versions`, whose name specifies the target type.
+    const NAME_OFFSET: usize = 16;
+
+    let name = input
+        .get(NAME_OFFSET..)
+        .map(read_cstr)
+        .transpose()?
+        .unwrap_or_default();
+    let Some(target_type) = TARGET_TYPES
+        .iter()
+        .find(|target_type| target_type.name == name)
+    else {
+        return_errno_with_message!(Errno::EINVAL, "the target type is not supported");
+    };
+
+    let mut output = Vec::new();
+    push_target_version(&mut output, target_type.name, target_type.version);
+    Ok(Some(output))
+}
+
+/// Finds the device specified by the UUID, the name, or the device number in the header.
+fn find_device(header: &DmIoctl) -> Result<Arc<MappedDevice>> {
+    let uuid = read_cstr(&header.uuid)?;
+    let name = read_cstr(&header.name)?;
+
+    let devices = DEVICES.lock();
+    let device = if !uuid.is_empty() {
+        if !name.is_empty() || header.dev != 0 {
+            return_errno_with_message!(Errno::EINVAL, "multiple device identifiers are given");
+        }
+        devices
+            .values()
+            .find(|device| device.state.lock().uuid == uuid)
+    } else if !name.is_empty() {
+        if header.dev != 0 {
+            return_errno_with_message!(Errno::EINVAL, "multiple device identifiers are given");
+        }
+        devices
+            .values()
+            .find(|device| device.state.lock().name == name)
+    } else {
+        let id = DeviceId::from_encoded_u64(header.dev);
+        devices.values().find(|device| Some(device.id) == id)
+    };
+
+    device
+        .cloned()
+        .ok_or_else(|| Error::with_message(Errno::ENXIO, "the mapped device does not exist"))
+}
+
+/// Fills the status of the device in the header.
+fn fill_status(header: &mut DmIoctl, device: &MappedDevice) {
+    let state = device.state.lock();
+
+    header.dev = device.id.as_encoded_u64();
+    header.open_count = 0;
+    header.event_nr = state.event_nr;
+
+    header.flags &= !(DM_SUSPEND_FLAG
+        | DM_READONLY_FLAG
+        | DM_ACTIVE_PRESENT_FLAG
+        | DM_INACTIVE_PRESENT_FLAG);
+    if state.is_suspended {
+        header.flags |= DM_SUSPEND_FLAG;
+    }
+    if let Some(live_table) = state.live_table.as_ref() {
+        header.flags |= DM_ACTIVE_PRESENT_FLAG;
+        if live_table.is_read_only() {
+            header.flags |= DM_READONLY_FLAG;
+        }
+    }
+    if state.inactive_table.is_some() {
+        header.flags |= DM_INACTIVE_PRESENT_FLAG;
+    }
+
+    let table = if header.flags & DM_QUERY_INACTIVE_TABLE_FLAG != 0 {
+        state.inactive_table.as_ref()
+    } else {
+        state.live_table.as_ref()
+    };
+    header.target_count = table.map_or(0, |table| table.targets().len() as u32);
+
+    header.name = [0; DM_NAME_LEN];
+    write_cstr(&mut header.name, &state.name);
+    header.uuid = [0; DM_UUID_LEN];
+    write_cstr(&mut header.uuid, &state.uuid);
+}
+
+/// Returns the live table, or the inactive table if it is queried.
+fn selected_table(header: &DmIoctl, device: &MappedDevice) -> Option<Arc<Table>> {
+    let state = device.state.lock();
+    if header.flags & DM_QUERY_INACTIVE_TABLE_FLAG != 0 {
+        state.inactive_table.clone()
+    } else {
+        state.live_table.clone()
+    }
+}
+
+fn read_spec(input: &[u8], offset: usize) -> Result<DmTargetSpec> {
+    input
+        .get(offset..)
+        .and_then(|bytes| bytes.get(..size_of::<DmTargetSpec>()))
+        .map(DmTargetSpec::from_bytes)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "the target spec is out of bounds"))
+}
+
+fn read_cstr(bytes: &[u8]) -> Result<String> {
+    let Ok(cstr) = CStr::from_bytes_until_nul(bytes) else {
+        return_errno_with_message!(Errno::EINVAL, "the string is not terminated");
+    };
+    let Ok(str) = cstr.to_str() else {
+        return_errno_with_message!(Errno::EINVAL, "the string is not valid UTF-8");
+    };
+
+    Ok(str.to_string())
+}
+
+/// Writes the string to the buffer, which must have zeros at the end.
+fn write_cstr(buf: &mut [u8], str: &str) {
+    let len = str.len().min(buf.len() - 1);
+    buf[..len].copy_from_slice(&str.as_bytes()[..len]);
+}
+
+fn push_cstr(output: &mut Vec<u8>, str: &str) {
+    output.extend_from_slice(str.as_bytes());
+    output.push(0);
+}
+
+fn push_target_version(output: &mut Vec<u8>, name: &str, version: [u32; 3]) {
+    // The entry is `struct dm_target_versions`, whose `next` is filled later.
+    output.extend_from_slice(&0u32.to_ne_bytes());
+    for number in version {
+        output.extend_from_slice(&number.to_ne_bytes());
+    }
+    push_cstr(output, name);
+    align_data(output);
+}
+
+fn write_u32(output: &mut [u8], offset: usize, val: u32) {
+    output[offset..offset + size_of::<u32>()].copy_from_slice(&val.to_ne_bytes());
+}
+
+fn align_data(output: &mut Vec<u8>) {
+    output.resize(output.len().next_multiple_of(DATA_ALIGN), 0);
+}
+
+/// The header of the device mapper ioctls; `struct dm_ioctl` in Linux.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/dm-ioctl.h#L129>
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct DmIoctl {
+    version: [u32; 3],
+    data_size: u32,
+    data_start: u32,
+    target_count: u32,
+    open_count: i32,
+    flags: u32,
+    event_nr: u32,
+    padding: u32,
+    dev: u64,
+    name: [u8; DM_NAME_LEN],
+    uuid: [u8; DM_UUID_LEN],
+    data: [u8; 7],
+}
+
+/// The specification of a target; `struct dm_target_spec` in Linux.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/dm-ioctl.h#L182>
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct DmTargetSpec {
+    sector_start: u64,
+    length: u64,
+    status: i32,
+    next: u32,
+    target_type: [u8; DM_MAX_TYPE_NAME],
+}
+
+mod ioctl_defs {
+    use super::DmIoctl;
+    use crate::util::ioctl::{InOutData, ioc};
+
+    // Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/dm-ioctl.h#L290>
+
+    pub(super) type Version          = ioc!(DM_VERSION,            0xfd, 0,  InOutData<DmIoctl>);
+    pub(super) type RemoveAll        = ioc!(DM_REMOVE_ALL,         0xfd, 1,  InOutData<DmIoctl>);
+    pub(super) type ListDevices      = ioc!(DM_LIST_DEVICES,       0xfd, 2,  InOutData<DmIoctl>);
+    pub(super) type DevCreate        = ioc!(DM_DEV_CREATE,         0xfd, 3,  InOutData<DmIoctl>);
+    pub(super) type DevRemove        = ioc!(DM_DEV_REMOVE,         0xfd, 4,  InOutData<DmIoctl>);
+    pub(super) type DevRename        = ioc!(DM_DEV_RENAME,         0xfd, 5,  InOutData<DmIoctl>);
+    pub(super) type DevSuspend       = ioc!(DM_DEV_SUSPEND,        0xfd, 6,  InOutData<DmIoctl>);
+    pub(super) type DevStatus        = ioc!(DM_DEV_STATUS,         0xfd, 7,  InOutData<DmIoctl>);
+    pub(super) type TableLoad        = ioc!(DM_TABLE_LOAD,         0xfd, 9,  InOutData<DmIoctl>);
+    pub(super) type TableClear       = ioc!(DM_TABLE_CLEAR,        0xfd, 10, InOutData<DmIoctl>);
+    pub(super) type TableDeps        = ioc!(DM_TABLE_DEPS,         0xfd, 11, InOutData<DmIoctl>);
+    pub(super) type TableStatus      = ioc!(DM_TABLE_STATUS,       0xfd, 12, InOutData<DmIoctl>);
+    pub(super) type ListVersions     = ioc!(DM_LIST_VERSIONS,      0xfd, 13, InOutData<DmIoctl>);
+    pub(super) type GetTargetVersion = ioc!(DM_GET_TARGET_VERSION, 0xfd, 17, InOutData<DmIoctl>);
+}
@@ -0,0 +1,61 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The linear target, which maps the sectors to a contiguous range of an underlying device.
+//!
+//! The parameters are `<device> <offset>`.
+//!
+//! Reference: <https://docs.kernel.org/admin-guide/device-mapper/linear.html>
+
+use device_id::DeviceId;
+
+use super::table::{DeviceRange, Target, TargetType, parse_u64};
+use crate::prelude::*;
+
+pub(super) const TARGET_TYPE: TargetType = TargetType {
+    name: "linear",
+    version: [1, 4, 0],
+    new: LinearTarget::new,
+};
+
+struct LinearTarget {
+    range: DeviceRange,
+}
+
+impl LinearTarget {
+    fn new(params: &str, len: u64) -> Result<Box<dyn Target>> {
+        let [device, offset] = params.split_ascii_whitespace().collect::<Vec<_>>()[..] else {
+            return_errno_with_message!(Errno::EINVAL, "the number of parameters is invalid");
+        };
+
+        let range = DeviceRange::open(device, parse_u64(offset)?, len)?;
+        Ok(Box::new(Self { range }))
+    }
+}
+
+impl Target for LinearTarget {
+    fn read(&self, sector: u64, buf: &mut [u8]) -> Result<()> {
+        self.range.read(sector, buf)
+    }
+
+    fn write(&self, sector: u64, buf: &[u8]) -> Result<()> {
+        self.range.write(sector, buf)
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.range.flush()
+    }
+
+    fn params(&self) -> String {
+        let id = self.range.device_id();
+        format!(
+            "{}:{} {}",
+            id.major().get(),
+            id.minor().get(),
+            self.range.offset()
+        )
+    }
+
+    fn deps(&self) -> Vec<DeviceId> {
+        vec![self.range.device_id()]
+    }
+}
@@ -0,0 +1,212 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The device mapper.
+//!
+//! The device mapper creates virtual block devices (`/dev/dm-<N>`), whose sectors are mapped to
+//! other block devices according to tables of targets. The mapped devices and their tables are
+//! managed by the ioctls on `/dev/mapper/control`.
+//!
+//! Each mapped device has a live table, which serves the I/O, and an inactive table, which is
+//! loaded by the user space. Resuming a device makes the inactive table live.
+//!
+//! Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/md/dm.c>
+
+mod crypt;
+mod ioctl;
+mod linear;
+mod table;
+
+use aster_block::{
+    BlockDevice, BlockDeviceMeta, MajorIdOwner,
+    bio::{BioEnqueueError, BioStatus, SubmittedBio},
+};
+use device_id::{DeviceId, MinorId};
+pub(super) use ioctl::ioctl;
+use spin::Once;
+use table::Table;
+
+use super::registry::block;
+use crate::prelude::*;
+
+static MAPPER_MAJOR: Once<MajorIdOwner> = Once::new();
+
+static DEVICES: Mutex<BTreeMap<u32, Arc<MappedDevice>>> = Mutex::new(BTreeMap::new());
+
+pub(super) fn init_in_first_kthread() {
+    // Like Linux, the major ID of mapped devices is dynamically allocated.
+    MAPPER_MAJOR.call_once(|| aster_block::allocate_major().unwrap());
+}
+
+/// A mapped device.
+pub(super) struct MappedDevice {
+    id: DeviceId,
+    /// The name of the block device (e.g., `dm-0`).
+    disk_name: String,
+    state: Mutex<DeviceState>,
+}
+
+struct DeviceState {
+    /// The name given by the user space.
+    name: String,
+    uuid: String,
+    live_table: Option<Arc<Table>>,
+    inactive_table: Option<Arc<Table>>,
+    is_suspended: bool,
+    /// The bios submitted while the device is suspended.
+    deferred_bios: Vec<SubmittedBio>,
+    event_nr: u32,
+}
+
+impl MappedDevice {
+    fn new(minor: u32, name: String, uuid: String) -> Arc<Self> {
+        let major = MAPPER_MAJOR.get().unwrap().get();
+
+        Arc::new(Self {
+            id: DeviceId::new(major, MinorId::new(minor)),
+            disk_name: format!("dm-{}", minor),
+            state: Mutex::new(DeviceState {
+                name,
+                uuid,
+                live_table: None,
+                inactive_table: None,
+                is_suspended: false,
+                deferred_bios: Vec::new(),
+                event_nr: 0,
+            }),
+        })
+    }
+
+    /// Suspends the device, so that the new I/O will be deferred until the device is resumed.
+    fn suspend(&self) {
+        self.state.lock().is_suspended = true;
+    }
+
+    /// Resumes the device, making the inactive table live if there is one.
+    fn resume(&self) {
+        let (table, deferred_bios) = {
+            let mut state = self.state.lock();
+            if let Some(inactive_table) = state.inactive_table.take() {
+                state.live_table = Some(inactive_table);
+            }
+            state.is_suspended = false;
+            (
+                state.live_table.clone(),
+                core::mem::take(&mut state.deferred_bios),
+            )
+        };
+
+        for bio in deferred_bios {
+            handle_bio(table.as_deref(), bio);
+        }
+    }
+}
+
+impl Debug for MappedDevice {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("MappedDevice")
+            .field("id", &self.id)
+            .field("disk_name", &self.disk_name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl BlockDevice for MappedDevice {
+    fn enqueue(&self, bio: SubmittedBio) -> core::result::Result<(), BioEnqueueError> {
+        let table = {
+            let mut state = self.state.lock();
+            if state.is_suspended {
+                state.deferred_bios.push(bio);
+                return Ok(());
+            }
+            state.live_table.clone()
+        };
+
+        handle_bio(table.as_deref(), bio);
+        Ok(())
+    }
+
+    fn metadata(&self) -> BlockDeviceMeta {
+        let nr_sectors = self
+            .state
+            .lock()
+            .live_table
+            .as_ref()
+            .map_or(0, |table| table.nr_sectors());
+
+        BlockDeviceMeta {
+            max_nr_segments_per_bio: usize::MAX,
+            nr_sectors: nr_sectors as usize,
+            supports_fua: false,
+            max_discard_sectors: 0,
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.disk_name
+    }
+
+    fn id(&self) -> DeviceId {
+        self.id
+    }
+
+    fn can_scan_partitions(&self) -> bool {
+        // Like Linux, mapped devices have no partitions. Partitions can be mapped via the
+        // linear target instead.
+        false
+    }
+}
+
+fn handle_bio(table: Option<&Table>, bio: SubmittedBio) {
+    let status = match table {
+        Some(table) => table.handle_bio(&bio),
+        None => BioStatus::IoError,
+    };
+    bio.complete(status);
+}
+
+/// Creates a mapped device with the name and the UUID.
+///
+/// If `minor` is `None`, the first unused minor ID will be chosen.
+fn create_device(name: String, uuid: String, minor: Option<u32>) -> Result<Arc<MappedDevice>> {
+    let mut devices = DEVICES.lock();
+
+    if devices.values().any(|device| {
+        let state = device.state.lock();
+        state.name == name || (!uuid.is_empty() && state.uuid == uuid)
+    }) {
+        return_errno_with_message!(Errno::EBUSY, "the name or the UUID is already in use");
+    }
+
+    let minor = match minor {
+        Some(minor) if devices.contains_key(&minor) => {
+            return_errno_with_message!(Errno::EBUSY, "the minor ID is already in use");
+        }
+        Some(minor) => minor,
+        None => (0..).find(|minor| !devices.contains_key(minor)).unwrap(),
+    };
+    if minor > MinorId::MAX.get() {
+        return_errno_with_message!(Errno::EINVAL, "the minor ID is too large");
+    }
+
+    let device = MappedDevice::new(minor, name, uuid);
+    block::register(device.clone())?;
+    devices.insert(minor, device.clone());
+
+    Ok(device)
+}
+
+/// Removes a mapped device.
+fn remove_device(device: &MappedDevice) -> Result<()> {
+    let mut devices = DEVICES.lock();
+
+    block::unregister(device.id)?;
+    devices.remove(&device.id.minor().get());
+
+    // Fail the deferred I/O since the device will never be resumed.
+    let deferred_bios = core::mem::take(&mut device.state.lock().deferred_bios);
+    for bio in deferred_bios {
+        bio.complete(BioStatus::IoError);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,325 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::ops::Range;
+
+use aster_block::{
+    BlockDevice, SECTOR_SIZE,
+    bio::{BioSegment, BioStatus, BioType, SubmittedBio},
+};
+use device_id::DeviceId;
+use ostd::{
+    mm::{VmIo, io::util::HasVmReaderWriter},
+    task::Task,
+};
+
+use super::{crypt, linear};
+use crate::{
+    fs::{file::InodeType, vfs::path::FsPath},
+    prelude::*,
+};
+
+/// A type of targets.
+pub(super) struct TargetType {
+    pub(super) name: &'static str,
+    pub(super) version: [u32; 3],
+    /// Creates a target from the parameters and the length in sectors.
+    pub(super) new: fn(&str, u64) -> Result<Box<dyn Target>>,
+}
+
+/// All supported types of targets.
+pub(super) const TARGET_TYPES: &[TargetType] = &[linear::TARGET_TYPE, crypt::TARGET_TYPE];
+
+/// A target, which maps a range of sectors of a mapped device.
+///
+/// The sectors passed to the methods are relative to the start of the target.
+pub(super) trait Target: Send + Sync {
+    /// Reads the sectors starting from `sector` into the buffer.
+    fn read(&self, sector: u64, buf: &mut [u8]) -> Result<()>;
+
+    /// Writes the buffer into the sectors starting from `sector`.
+    fn write(&self, sector: u64, buf: &[u8]) -> Result<()>;
+
+    /// Flushes the volatile write cache of the underlying devices.
+    fn flush(&self) -> Result<()>;
+
+    /// Returns the parameters of the target, which are reported in the table status.
+    fn params(&self) -> String;
+
+    /// Returns the IDs of the underlying devices.
+    fn deps(&self) -> Vec<DeviceId>;
+}
+
+/// The specification of a target in a table to be loaded.
+pub(super) struct TargetSpec {
+    pub(super) start: u64,
+    pub(super) len: u64,
+    pub(super) type_name: String,
+    pub(super) params: String,
+}
+
+/// A mapping table, which consists of targets covering the sectors of a mapped device.
+pub(super) struct Table {
+    targets: Vec<TargetEntry>,
+    nr_sectors: u64,
+    is_read_only: bool,
+}
+
+pub(super) struct TargetEntry {
+    pub(super) start: u64,
+    pub(super) len: u64,
+    pub(super) type_: &'static TargetType,
+    pub(super) target: Box<dyn Target>,
+}
+
+impl Table {
+    /// Creates a table from the specifications of the targets.
+    ///
+    /// The targets must be sorted and contiguous, starting from the first sector.
+    pub(super) fn new(specs: Vec<TargetSpec>, is_read_only: bool) -> Result<Self> {
+        let mut targets = Vec::with_capacity(specs.len());
+        let mut nr_sectors = 0u64;
+
+        for spec in specs {
+            if spec.start != nr_sectors {
+                return_errno_with_message!(Errno::EINVAL, "the targets are not contiguous");
+            }
+            if spec.len == 0 {
+                return_errno_with_message!(Errno::EINVAL, "the target is empty");
+            }
+            nr_sectors = nr_sectors
+                .checked_add(spec.len)
+                .ok_or_else(|| Error::with_message(Errno::EINVAL, "the table is too large"))?;
+
+            let Some(type_) = TARGET_TYPES
+                .iter()
+                .find(|type_| type_.name == spec.type_name)
+            else {
+                return_errno_with_message!(Errno::EINVAL, "the target type is not supported");
+            };
+            let target = (type_.new)(&spec.params, spec.len)?;
+
+            targets.push(TargetEntry {
+                start: spec.start,
+                len: spec.len,
+                type_,
+                target,
+            });
+        }
+
+        if targets.is_empty() {
+            return_errno_with_message!(Errno::EINVAL, "the table has no targets");
+        }
+
+        Ok(Self {
+            targets,
+            nr_sectors,
+            is_read_only,
+        })
+    }
+
+    pub(super) fn targets(&self) -> &[TargetEntry] {
+        &self.targets
+    }
+
+    pub(super) fn nr_sectors(&self) -> u64 {
+        self.nr_sectors
+    }
+
+    pub(super) fn is_read_only(&self) -> bool {
+        self.is_read_only
+    }
+
+    /// Returns the IDs of all underlying devices, without duplicates.
+    pub(super) fn deps(&self) -> Vec<DeviceId> {
+        let mut deps: Vec<DeviceId> = Vec::new();
+        for entry in self.targets.iter() {
+            for dep in entry.target.deps() {
+                if !deps.contains(&dep) {
+                    deps.push(dep);
+                }
+            }
+        }
+        deps
+    }
+
+    pub(super) fn handle_bio(&self, bio: &SubmittedBio) -> BioStatus {
+        let type_ = bio.type_();
+        match type_ {
+            BioType::Read | BioType::Write => (),
+            BioType::Flush => {
+                return match self.targets.iter().try_for_each(|entry| entry.target.flush()) {
+                    Ok(()) => BioStatus::Complete,
+                    Err(_) => BioStatus::IoError,
+                };
+            }
+            BioType::Discard => return BioStatus::NotSupported,
+        }
+
+        if type_ == BioType::Write && self.is_read_only {
+            return BioStatus::IoError;
+        }
+
+        let start_sid = bio.sid_range().start.to_raw() + bio.sid_offset();
+        let end_sid = bio.sid_range().end.to_raw() + bio.sid_offset();
+        if end_sid > self.nr_sectors {
+            return BioStatus::IoError;
+        }
+
+        let mut sector = start_sid;
+        for segment in bio.segments() {
+            let res = if type_ == BioType::Read {
+                self.read_segment(sector, segment)
+            } else {
+                self.write_segment(sector, segment)
+            };
+            if res.is_err() {
+                return BioStatus::IoError;
+            }
+            sector += (segment.nbytes() / SECTOR_SIZE) as u64;
+        }
+
+        BioStatus::Complete
+    }
+
+    fn read_segment(&self, sector: u64, segment: &BioSegment) -> Result<()> {
+        let mut buf = vec![0u8; segment.nbytes()];
+        self.for_each_target(sector, buf.len(), |entry, target_sector, range| {
+            entry.target.read(target_sector, &mut buf[range])
+        })?;
+
+        segment.writer()?.write(&mut VmReader::from(buf.as_slice()));
+        Ok(())
+    }
+
+    fn write_segment(&self, sector: u64, segment: &BioSegment) -> Result<()> {
+        let mut buf = vec![0u8; segment.nbytes()];
+        segment.reader()?.read(&mut VmWriter::from(buf.as_mut_slice()));
+
+        self.for_each_target(sector, buf.len(), |entry, target_sector, range| {
+            entry.target.write(target_sector, &buf[range])
+        })
+    }
+
+    /// Splits the I/O of `len` bytes starting from `sector` by the targets.
+    ///
+    /// For each target, `f` will be called with the start sector relative to the target and the
+    /// byte range within the I/O.
+    fn for_each_target(
+        &self,
+        mut sector: u64,
+        len: usize,
+        mut f: impl FnMut(&TargetEntry, u64, Range<usize>) -> Result<()>,
+    ) -> Result<()> {
+        debug_assert!(len.is_multiple_of(SECTOR_SIZE));
+
+        let mut pos = 0;
+        while pos < len {
+            let index = self
+                .targets
+                .partition_point(|entry| entry.start + entry.len <= sector);
+            let entry = &self.targets[index];
+
+            let nr_sectors =
+                (((len - pos) / SECTOR_SIZE) as u64).min(entry.start + entry.len - sector);
+            let nbytes = nr_sectors as usize * SECTOR_SIZE;
+            f(entry, sector - entry.start, pos..pos + nbytes)?;
+
+            pos += nbytes;
+            sector += nr_sectors;
+        }
+
+        Ok(())
+    }
+}
+
+/// A range of sectors on an underlying device.
+pub(super) struct DeviceRange {
+    device: Arc<dyn BlockDevice>,
+    /// The start sector on the underlying device.
+    offset: u64,
+}
+
+impl DeviceRange {
+    /// Opens the underlying device in the range, which starts from `offset` and has `len`
+    /// sectors.
+    ///
+    /// The device can be specified either by `<major>:<minor>` or by the path of the device file.
+    pub(super) fn open(device: &str, offset: u64, len: u64) -> Result<Self> {
+        let device = lookup_device(device)?;
+
+        let nr_sectors = device.metadata().nr_sectors as u64;
+        if offset
+            .checked_add(len)
+            .is_none_or(|end_sector| end_sector > nr_sectors)
+        {
+            return_errno_with_message!(Errno::EINVAL, "the range exceeds the device");
+        }
+
+        Ok(Self { device, offset })
+    }
+
+    pub(super) fn read(&self, sector: u64, buf: &mut [u8]) -> Result<()> {
+        self.device
+            .read_bytes(self.byte_offset(sector), buf)
+            .map_err(Error::from)
+    }
+
+    pub(super) fn write(&self, sector: u64, buf: &[u8]) -> Result<()> {
+        self.device
+            .write_bytes(self.byte_offset(sector), buf)
+            .map_err(Error::from)
+    }
+
+    pub(super) fn flush(&self) -> Result<()> {
+        match self.device.sync()? {
+            BioStatus::Complete => Ok(()),
+            status => Err(status.into()),
+        }
+    }
+
+    pub(super) fn device_id(&self) -> DeviceId {
+        self.device.id()
+    }
+
+    pub(super) fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    fn byte_offset(&self, sector: u64) -> usize {
+        (self.offset + sector) as usize * SECTOR_SIZE
+    }
+}
+
+fn lookup_device(device: &str) -> Result<Arc<dyn BlockDevice>> {
+    let id = if let Some((major, minor)) = device.split_once(':') {
+        let (Ok(major), Ok(minor)) = (major.parse::<u32>(), minor.parse::<u32>()) else {
+            return_errno_with_message!(Errno::EINVAL, "the device number is invalid");
+        };
+        DeviceId::from_encoded_u64(device_id::encode_device_numbers(major, minor))
+    } else {
+        let path = {
+            let fs_path = FsPath::try_from(device)?;
+            let task = Task::current().unwrap();
+            let thread_local = task.as_thread_local().unwrap();
+            thread_local
+                .borrow_fs()
+                .resolver()
+                .read()
+                .lookup(&fs_path)?
+        };
+        if path.type_() != InodeType::BlockDevice {
+            return_errno_with_message!(Errno::ENOTBLK, "the path is not a block device");
+        }
+        path.metadata().self_dev_id
+    };
+
+    id.and_then(aster_block::lookup)
+        .ok_or_else(|| Error::with_message(Errno::ENXIO, "the device is not found"))
+}
+
+/// Parses a number in the table parameters.
+pub(super) fn parse_u64(param: &str) -> Result<u64> {
+    param
+        .parse()
+        .map_err(|_| Error::with_message(Errno::EINVAL, "the number is invalid"))
+}
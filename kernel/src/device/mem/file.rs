@@ -2,7 +2,7 @@
 
 use alloc::vec;
 
-use ostd::mm::{FallibleVmWrite, VmReader, VmWriter};
+use ostd::mm::{FallibleVmRead, FallibleVmWrite, VmReader, VmWriter};
 
 use crate::{
     error::Errno,
@@ -39,9 +39,34 @@ pub fn geturandom(writer: &mut VmWriter) -> Result<usize> {
     Ok(written_bytes)
 }
 
-// TODO: Support true randomness by collecting environment noise.
+// Like Linux since 5.18, `/dev/random` never blocks once the generator is seeded, which happens at
+// boot, so it behaves the same as `/dev/urandom`.
 pub use geturandom as getrandom;
 
+/// Mixes the written data into the input pool of the random number generator.
+fn write_randomness(reader: &mut VmReader) -> Result<usize> {
+    const IO_CAPABILITY: usize = 4096;
+
+    let mut buffer = vec![0; reader.remain().min(IO_CAPABILITY)];
+    let mut read_bytes = 0;
+
+    while reader.has_remain() {
+        match reader.read_fallible(&mut VmWriter::from(buffer.as_mut_slice())) {
+            Ok(len) => {
+                random::add_randomness(&buffer[..len]);
+                read_bytes += len;
+            }
+            Err((err, 0)) if read_bytes == 0 => return Err(err.into()),
+            Err((_, len)) => {
+                random::add_randomness(&buffer[..len]);
+                return Ok(read_bytes + len);
+            }
+        }
+    }
+
+    Ok(read_bytes)
+}
+
 #[derive(Debug, Copy, Clone)]
 #[expect(dead_code)]
 pub(super) enum MemFile {
@@ -129,7 +154,8 @@ impl InodeIo for MemFile {
         _status_flags: StatusFlags,
     ) -> Result<usize> {
         match self {
-            MemFile::Null | MemFile::Random | MemFile::Urandom | MemFile::Zero => {
+            MemFile::Random | MemFile::Urandom => write_randomness(reader),
+            MemFile::Null | MemFile::Zero => {
                 let len = reader.remain();
                 reader.skip(len);
                 Ok(len)
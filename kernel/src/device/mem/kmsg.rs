@@ -0,0 +1,239 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The `/dev/kmsg` device.
+//!
+//! Reading the device exports the records in the kernel message ring buffer, one record per
+//! `read()`, and writing to the device appends a record to the buffer. Each opened file has its
+//! own read position, which starts at the oldest record.
+//!
+//! Like Linux, the messages written to each opened file are rate-limited, so a misbehaving
+//! process cannot flood the ring buffer.
+//!
+//! Reference: <https://www.kernel.org/doc/Documentation/ABI/testing/dev-kmsg>
+
+use alloc::format;
+use core::{
+    fmt::Write,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use aster_logger::{
+    RateLimit,
+    console_level::default_message_loglevel,
+    kmsg::{
+        self, KMSG_APPEND_HANDLER_FN, KMSG_FACILITY_USER, KMSG_TEXT_MAX, KmsgCursor, KmsgError,
+        KmsgRecord,
+    },
+};
+use ostd::timer::Jiffies;
+use spin::Once;
+
+use crate::{
+    events::IoEvents,
+    fs::{
+        file::{FileIo, StatusFlags},
+        vfs::inode::InodeIo,
+    },
+    prelude::*,
+    process::signal::{PollHandle, Pollable, Pollee},
+};
+
+/// The pollee that is notified whenever a record is appended to the kernel message ring buffer.
+static KMSG_POLLEE: Once<Pollee> = Once::new();
+
+/// Whether some CPU is notifying [`KMSG_POLLEE`].
+static IS_NOTIFYING: AtomicBool = AtomicBool::new(false);
+
+/// An opened `/dev/kmsg` file.
+pub(super) struct KmsgFile {
+    cursor: Mutex<KmsgCursor>,
+    ratelimit: RateLimit,
+}
+
+impl KmsgFile {
+    pub(super) fn new() -> Self {
+        Self {
+            cursor: Mutex::new(KmsgCursor::first()),
+            ratelimit: RateLimit::new(),
+        }
+    }
+
+    fn try_read(&self, writer: &mut VmWriter) -> Result<usize> {
+        let mut cursor = self.cursor.lock();
+
+        // Read from a copy, so the position is not advanced if the buffer is too small.
+        let mut next_cursor = *cursor;
+        let record = match next_cursor.read_record() {
+            Ok(record) => record,
+            Err(KmsgError::Overwritten) => {
+                *cursor = next_cursor;
+                return_errno_with_message!(Errno::EPIPE, "the record has been overwritten");
+            }
+            Err(KmsgError::NoRecord) => {
+                return_errno_with_message!(Errno::EAGAIN, "no record is available");
+            }
+        };
+
+        let line = format_record(&record);
+        if line.len() > writer.avail() {
+            return_errno_with_message!(Errno::EINVAL, "the buffer is too small for the record");
+        }
+        writer.write_fallible(&mut VmReader::from(line.as_bytes()))?;
+        *cursor = next_cursor;
+
+        Ok(line.len())
+    }
+
+    fn check_io_events(&self) -> IoEvents {
+        let cursor = self.cursor.lock();
+        if cursor.is_overwritten() {
+            IoEvents::IN | IoEvents::RDNORM | IoEvents::ERR | IoEvents::PRI
+        } else if cursor.has_record() {
+            IoEvents::IN | IoEvents::RDNORM
+        } else {
+            IoEvents::empty()
+        }
+    }
+}
+
+impl Pollable for KmsgFile {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        // The events depend on the read position of each file, so they cannot be cached in the
+        // shared pollee.
+        if let Some(poller) = poller {
+            register_kmsg_poller(poller, mask);
+        }
+
+        (self.check_io_events() | IoEvents::OUT | IoEvents::WRNORM) & mask
+    }
+}
+
+impl InodeIo for KmsgFile {
+    fn read_at(
+        &self,
+        _offset: usize,
+        writer: &mut VmWriter,
+        status_flags: StatusFlags,
+    ) -> Result<usize> {
+        if status_flags.contains(StatusFlags::O_NONBLOCK) {
+            self.try_read(writer)
+        } else {
+            self.wait_events(IoEvents::IN, None, || self.try_read(writer))
+        }
+    }
+
+    fn write_at(
+        &self,
+        _offset: usize,
+        reader: &mut VmReader,
+        _status_flags: StatusFlags,
+    ) -> Result<usize> {
+        let len = reader.remain();
+        if len > KMSG_TEXT_MAX {
+            return_errno_with_message!(Errno::EINVAL, "the message is too long");
+        }
+
+        let mut message = vec![0u8; len];
+        reader.read_fallible(&mut VmWriter::from(message.as_mut_slice()))?;
+
+        // Like Linux, the messages exceeding the rate limit are dropped silently.
+        if !self.ratelimit.check("kmsg") {
+            return Ok(len);
+        }
+
+        let (facility, level, text) = parse_priority_prefix(&message);
+        let text = text.strip_suffix(b"\n").unwrap_or(text);
+        kmsg::append(facility, level, Jiffies::elapsed().as_duration(), text);
+
+        Ok(len)
+    }
+}
+
+impl FileIo for KmsgFile {
+    fn check_seekable(&self) -> Result<()> {
+        // TODO: Support `SEEK_SET`, `SEEK_END`, and `SEEK_DATA` to move the read position.
+        Ok(())
+    }
+
+    fn is_offset_aware(&self) -> bool {
+        false
+    }
+}
+
+/// Registers a poller that is notified whenever a record is appended to the kernel message ring
+/// buffer.
+pub fn register_kmsg_poller(poller: &mut PollHandle, mask: IoEvents) {
+    KMSG_POLLEE.get().unwrap().register_poller(poller, mask);
+}
+
+/// Parses the optional `<N>` priority prefix of a message written from user space.
+///
+/// Like Linux, messages without a facility are attributed to `LOG_USER`, since user space must
+/// not be able to forge kernel messages.
+fn parse_priority_prefix(message: &[u8]) -> (u8, u8, &[u8]) {
+    let default = (KMSG_FACILITY_USER, default_message_loglevel(), message);
+
+    let Some(rest) = message.strip_prefix(b"<") else {
+        return default;
+    };
+    let Some(end) = rest.iter().position(|byte| *byte == b'>') else {
+        return default;
+    };
+    let Some(priority) = core::str::from_utf8(&rest[..end])
+        .ok()
+        .and_then(|digits| digits.parse::<u32>().ok())
+    else {
+        return default;
+    };
+
+    let facility = match (priority >> 3) as u8 {
+        0 => KMSG_FACILITY_USER,
+        facility => facility,
+    };
+    (facility, (priority & 0x7) as u8, &rest[end + 1..])
+}
+
+/// Formats a record in the `/dev/kmsg` format.
+///
+/// The format is `<priority>,<sequence>,<timestamp>,<flags>;<text>\n`, where non-printable
+/// characters in the text are escaped as `\xNN`.
+fn format_record(record: &KmsgRecord) -> String {
+    let mut line = format!(
+        "{},{},{},-;",
+        record.priority(),
+        record.seq(),
+        record.timestamp().as_micros()
+    );
+
+    for byte in record.text() {
+        if *byte < b' ' || *byte >= 0x7f || *byte == b'\\' {
+            let _ = write!(line, "\\x{:02x}", byte);
+        } else {
+            line.push(*byte as char);
+        }
+    }
+    line.push('\n');
+
+    line
+}
+
+fn handle_append() {
+    let Some(pollee) = KMSG_POLLEE.get() else {
+        return;
+    };
+
+    // Notifying the pollee may log messages (e.g., when waking up tasks), which would call this
+    // function recursively and deadlock on the lock of the pollee. Skipping the notification is
+    // safe because the ongoing notification has not finished yet, so any poller registered after
+    // it finishes will see the new record when checking the events.
+    if IS_NOTIFYING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    pollee.notify(IoEvents::IN | IoEvents::RDNORM);
+    IS_NOTIFYING.store(false, Ordering::SeqCst);
+}
+
+pub(super) fn init_in_first_kthread() {
+    KMSG_POLLEE.call_once(Pollee::new);
+    KMSG_APPEND_HANDLER_FN.call_once(|| handle_append);
+}
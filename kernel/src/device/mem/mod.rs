@@ -19,12 +19,15 @@
 //! See <https://www.kernel.org/doc/Documentation/admin-guide/devices.txt>.
 
 mod file;
+mod kmsg;
 
 use alloc::sync::Arc;
 
 use device_id::{DeviceId, MajorId, MinorId};
 use file::MemFile;
 pub use file::{getrandom, geturandom};
+use kmsg::KmsgFile;
+pub use kmsg::register_kmsg_poller;
 use spin::Once;
 
 use super::{
@@ -65,8 +68,15 @@ impl Device for MemDevice {
         Some(self.file.name().into())
     }
 
+    fn class(&self) -> &'static str {
+        "mem"
+    }
+
     fn open(&self) -> Result<Box<dyn FileIo>> {
-        Ok(Box::new(self.file))
+        match self.file {
+            MemFile::Kmsg => Ok(Box::new(KmsgFile::new())),
+            _ => Ok(Box::new(self.file)),
+        }
     }
 }
 
@@ -75,7 +85,10 @@ static MEM_MAJOR: Once<MajorIdOwner> = Once::new();
 pub(super) fn init_in_first_kthread() {
     MEM_MAJOR.call_once(|| acquire_major(MajorId::new(1)).unwrap());
 
+    kmsg::init_in_first_kthread();
+
     register(Arc::new(MemDevice::new(MemFile::Full))).unwrap();
+    register(Arc::new(MemDevice::new(MemFile::Kmsg))).unwrap();
     register(Arc::new(MemDevice::new(MemFile::Null))).unwrap();
     register(Arc::new(MemDevice::new(MemFile::Random))).unwrap();
     register(Arc::new(MemDevice::new(MemFile::Urandom))).unwrap();
@@ -0,0 +1,98 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use device_id::{DeviceId, MinorId};
+
+use crate::{
+    device::{Device, DeviceType, mapper},
+    events::IoEvents,
+    fs::{
+        file::{FileIo, StatusFlags},
+        vfs::inode::InodeIo,
+    },
+    prelude::*,
+    process::signal::{PollHandle, Pollable},
+    util::ioctl::RawIoctl,
+};
+
+const DM_CONTROL_MINOR: u32 = 236;
+
+/// The `/dev/mapper/control` device, which manages the mapped devices.
+#[derive(Debug)]
+pub struct DmControl {
+    id: DeviceId,
+}
+
+impl DmControl {
+    pub fn new() -> Arc<Self> {
+        let major = super::MISC_MAJOR.get().unwrap().get();
+        let minor = MinorId::new(DM_CONTROL_MINOR);
+
+        let id = DeviceId::new(major, minor);
+        Arc::new(Self { id })
+    }
+}
+
+impl Device for DmControl {
+    fn type_(&self) -> DeviceType {
+        DeviceType::Char
+    }
+
+    fn id(&self) -> DeviceId {
+        self.id
+    }
+
+    fn devtmpfs_path(&self) -> Option<String> {
+        Some("mapper/control".into())
+    }
+
+    fn class(&self) -> &'static str {
+        "misc"
+    }
+
+    fn open(&self) -> Result<Box<dyn FileIo>> {
+        Ok(Box::new(DmControlFile))
+    }
+}
+
+struct DmControlFile;
+
+impl Pollable for DmControlFile {
+    fn poll(&self, mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
+        let events = IoEvents::IN | IoEvents::OUT;
+        events & mask
+    }
+}
+
+impl InodeIo for DmControlFile {
+    fn read_at(
+        &self,
+        _offset: usize,
+        _writer: &mut VmWriter,
+        _status_flags: StatusFlags,
+    ) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "the file is not valid for reading")
+    }
+
+    fn write_at(
+        &self,
+        _offset: usize,
+        _reader: &mut VmReader,
+        _status_flags: StatusFlags,
+    ) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "the file is not valid for writing")
+    }
+}
+
+impl FileIo for DmControlFile {
+    fn check_seekable(&self) -> Result<()> {
+        return_errno_with_message!(Errno::ESPIPE, "seek is not supported")
+    }
+
+    fn is_offset_aware(&self) -> bool {
+        false
+    }
+
+    fn ioctl(&self, raw_ioctl: RawIoctl) -> Result<i32> {
+        mapper::ioctl(raw_ioctl)
+    }
+}
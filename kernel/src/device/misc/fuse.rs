@@ -0,0 +1,49 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use device_id::{DeviceId, MinorId};
+
+use crate::{
+    device::{Device, DeviceType},
+    fs::{file::FileIo, fuse::FuseDevFile},
+    prelude::*,
+};
+
+const FUSE_MINOR: u32 = 229;
+
+/// The `/dev/fuse` device.
+#[derive(Debug)]
+pub struct Fuse {
+    id: DeviceId,
+}
+
+impl Fuse {
+    pub fn new() -> Arc<Self> {
+        let major = super::MISC_MAJOR.get().unwrap().get();
+        let minor = MinorId::new(FUSE_MINOR);
+
+        let id = DeviceId::new(major, minor);
+        Arc::new(Self { id })
+    }
+}
+
+impl Device for Fuse {
+    fn type_(&self) -> DeviceType {
+        DeviceType::Char
+    }
+
+    fn id(&self) -> DeviceId {
+        self.id
+    }
+
+    fn devtmpfs_path(&self) -> Option<String> {
+        Some("fuse".into())
+    }
+
+    fn class(&self) -> &'static str {
+        "misc"
+    }
+
+    fn open(&self) -> Result<Box<dyn FileIo>> {
+        Ok(Box::new(FuseDevFile::new()))
+    }
+}
@@ -0,0 +1,165 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The hardware random number generator device (`/dev/hwrng`).
+//!
+//! Reading the device returns the random bytes from the current hardware random number generator
+//! directly. Besides, a kernel thread feeds the random bytes from the current generator into the
+//! random number generator of the kernel periodically.
+
+use core::time::Duration;
+
+use device_id::{DeviceId, MinorId};
+use ostd::{mm::FallibleVmWrite, sync::WaitQueue};
+
+use crate::{
+    device::{Device, DeviceType},
+    events::IoEvents,
+    fs::{
+        file::{FileIo, StatusFlags},
+        vfs::inode::InodeIo,
+    },
+    prelude::*,
+    process::signal::{PollHandle, Pollable},
+    thread::kernel_thread::ThreadOptions,
+    util::random,
+};
+
+/// The minor number of `/dev/hwrng`.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/linux/miscdevice.h>.
+const HWRNG_MINOR: u32 = 183;
+
+/// The number of random bytes that are read from the device at a time.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/char/hw_random/core.c>.
+const RNG_BUFFER_SIZE: usize = 32;
+
+/// The minimum interval between two reads of the feeding thread.
+const MIN_FILL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The `/dev/hwrng` device.
+#[derive(Debug)]
+pub struct HwRng {
+    id: DeviceId,
+}
+
+impl HwRng {
+    pub fn new() -> Arc<Self> {
+        let major = super::MISC_MAJOR.get().unwrap().get();
+        let minor = MinorId::new(HWRNG_MINOR);
+
+        let id = DeviceId::new(major, minor);
+        Arc::new(Self { id })
+    }
+}
+
+impl Device for HwRng {
+    fn type_(&self) -> DeviceType {
+        DeviceType::Char
+    }
+
+    fn id(&self) -> DeviceId {
+        self.id
+    }
+
+    fn devtmpfs_path(&self) -> Option<String> {
+        Some("hwrng".into())
+    }
+
+    fn class(&self) -> &'static str {
+        "misc"
+    }
+
+    fn open(&self) -> Result<Box<dyn FileIo>> {
+        Ok(Box::new(HwRngFile))
+    }
+}
+
+struct HwRngFile;
+
+impl Pollable for HwRngFile {
+    fn poll(&self, mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
+        let events = IoEvents::IN | IoEvents::OUT;
+        events & mask
+    }
+}
+
+impl InodeIo for HwRngFile {
+    fn read_at(
+        &self,
+        _offset: usize,
+        writer: &mut VmWriter,
+        _status_flags: StatusFlags,
+    ) -> Result<usize> {
+        let Some((_, device)) = aster_hwrng::current_device() else {
+            return_errno_with_message!(Errno::ENODEV, "no hardware random number generators exist");
+        };
+
+        let mut buffer = [0u8; RNG_BUFFER_SIZE];
+        let mut written_bytes = 0;
+
+        while writer.has_avail() {
+            let len = buffer.len().min(writer.avail());
+            let len = device.read(&mut buffer[..len]);
+            if len == 0 {
+                break;
+            }
+
+            match writer.write_fallible(&mut VmReader::from(&buffer[..len])) {
+                Ok(len) => written_bytes += len,
+                Err((err, 0)) if written_bytes == 0 => return Err(err.into()),
+                Err((_, len)) => return Ok(written_bytes + len),
+            }
+        }
+
+        Ok(written_bytes)
+    }
+
+    fn write_at(
+        &self,
+        _offset: usize,
+        _reader: &mut VmReader,
+        _status_flags: StatusFlags,
+    ) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "the file is not valid for writing")
+    }
+}
+
+impl FileIo for HwRngFile {
+    fn check_seekable(&self) -> Result<()> {
+        return_errno_with_message!(Errno::ESPIPE, "seek is not supported")
+    }
+
+    fn is_offset_aware(&self) -> bool {
+        false
+    }
+}
+
+/// Spawns the kernel thread that feeds the random bytes from the current hardware random number
+/// generator into the random number generator of the kernel.
+///
+/// Like Linux, the thread reads once per reseed interval, so that each reseed uses new random
+/// bytes from the generator.
+pub(super) fn spawn_fill_thread() {
+    if aster_hwrng::current_device().is_none() {
+        return;
+    }
+
+    let task_fn = || {
+        let wait_queue = WaitQueue::new();
+        let mut buffer = [0u8; RNG_BUFFER_SIZE];
+
+        loop {
+            if let Some((_, device)) = aster_hwrng::current_device() {
+                let len = device.read(&mut buffer);
+                random::add_randomness(&buffer[..len]);
+            }
+
+            // The interval can be set to zero, which must not make the thread busy.
+            let interval = random::min_reseed_interval().max(MIN_FILL_INTERVAL);
+            let _ = wait_queue.wait_until_or_timeout(|| -> Option<()> { None }, &interval);
+        }
+    };
+
+    ThreadOptions::new(task_fn).spawn();
+}
@@ -0,0 +1,132 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use device_id::{DeviceId, MinorId};
+
+use crate::{
+    device::{Device, DeviceType, loop_device},
+    events::IoEvents,
+    fs::{
+        file::{FileIo, StatusFlags},
+        vfs::inode::InodeIo,
+    },
+    prelude::*,
+    process::signal::{PollHandle, Pollable},
+    util::ioctl::{RawIoctl, dispatch_ioctl},
+};
+
+const LOOP_CONTROL_MINOR: u32 = 237;
+
+/// The `/dev/loop-control` device.
+#[derive(Debug)]
+pub struct LoopControl {
+    id: DeviceId,
+}
+
+impl LoopControl {
+    pub fn new() -> Arc<Self> {
+        let major = super::MISC_MAJOR.get().unwrap().get();
+        let minor = MinorId::new(LOOP_CONTROL_MINOR);
+
+        let id = DeviceId::new(major, minor);
+        Arc::new(Self { id })
+    }
+}
+
+impl Device for LoopControl {
+    fn type_(&self) -> DeviceType {
+        DeviceType::Char
+    }
+
+    fn id(&self) -> DeviceId {
+        self.id
+    }
+
+    fn devtmpfs_path(&self) -> Option<String> {
+        Some("loop-control".into())
+    }
+
+    fn class(&self) -> &'static str {
+        "misc"
+    }
+
+    fn open(&self) -> Result<Box<dyn FileIo>> {
+        Ok(Box::new(LoopControlFile))
+    }
+}
+
+struct LoopControlFile;
+
+impl Pollable for LoopControlFile {
+    fn poll(&self, mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
+        let events = IoEvents::IN | IoEvents::OUT;
+        events & mask
+    }
+}
+
+impl InodeIo for LoopControlFile {
+    fn read_at(
+        &self,
+        _offset: usize,
+        _writer: &mut VmWriter,
+        _status_flags: StatusFlags,
+    ) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "the file is not valid for reading")
+    }
+
+    fn write_at(
+        &self,
+        _offset: usize,
+        _reader: &mut VmReader,
+        _status_flags: StatusFlags,
+    ) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "the file is not valid for writing")
+    }
+}
+
+impl FileIo for LoopControlFile {
+    fn check_seekable(&self) -> Result<()> {
+        return_errno_with_message!(Errno::ESPIPE, "seek is not supported")
+    }
+
+    fn is_offset_aware(&self) -> bool {
+        false
+    }
+
+    fn ioctl(&self, raw_ioctl: RawIoctl) -> Result<i32> {
+        use ioctl_defs::*;
+
+        let index = dispatch_ioctl!(match raw_ioctl {
+            cmd @ Add => {
+                // A negative index asks for the first unused index.
+                let index = u32::try_from(cmd.get()).ok();
+                loop_device::add_device(index)?
+            }
+            cmd @ Remove => {
+                let Ok(index) = u32::try_from(cmd.get()) else {
+                    return_errno_with_message!(Errno::ENODEV, "the loop device does not exist");
+                };
+                loop_device::remove_device(index)?;
+                0
+            }
+            _cmd @ GetFree => {
+                loop_device::get_free_device()?
+            }
+            _ => return_errno_with_message!(
+                Errno::ENOTTY,
+                "the ioctl command is not supported by the loop control device"
+            ),
+        });
+
+        Ok(index as i32)
+    }
+}
+
+mod ioctl_defs {
+    use crate::util::ioctl::{InData, NoData, PassByVal, ioc};
+
+    // Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/loop.h#L125>
+
+    pub(super) type Add     = ioc!(LOOP_CTL_ADD,      0x4C80, InData<i32, PassByVal>);
+    pub(super) type Remove  = ioc!(LOOP_CTL_REMOVE,   0x4C81, InData<i32, PassByVal>);
+    pub(super) type GetFree = ioc!(LOOP_CTL_GET_FREE, 0x4C82, NoData);
+}
@@ -9,6 +9,12 @@ use spin::Once;
 
 use super::registry::char::{MajorIdOwner, acquire_major};
 
+pub mod dm_control;
+pub mod fuse;
+pub mod hwrng;
+pub mod loop_control;
+pub mod tun;
+pub mod watchdog;
 #[cfg(all(target_arch = "x86_64", feature = "cvm_guest"))]
 pub mod tdxguest;
 
@@ -17,6 +23,16 @@ static MISC_MAJOR: Once<MajorIdOwner> = Once::new();
 pub(super) fn init_in_first_kthread() {
     MISC_MAJOR.call_once(|| acquire_major(MajorId::new(10)).unwrap());
 
+    super::registry::char::register(dm_control::DmControl::new()).unwrap();
+    super::registry::char::register(fuse::Fuse::new()).unwrap();
+    super::registry::char::register(hwrng::HwRng::new()).unwrap();
+    super::registry::char::register(loop_control::LoopControl::new()).unwrap();
+    super::registry::char::register(tun::Tun::new()).unwrap();
+    super::registry::char::register(watchdog::Watchdog::new()).unwrap();
+
+    hwrng::spawn_fill_thread();
+    watchdog::init_softdog();
+
     #[cfg(target_arch = "x86_64")]
     ostd::if_tdx_enabled!({
         super::registry::char::register(tdxguest::TdxGuest::new()).unwrap();
@@ -62,6 +62,10 @@ impl Device for TdxGuest {
         Some("tdx_guest".into())
     }
 
+    fn class(&self) -> &'static str {
+        "misc"
+    }
+
     fn open(&self) -> Result<Box<dyn FileIo>> {
         Ok(Box::new(TdxGuestFile))
     }
@@ -0,0 +1,50 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use device_id::{DeviceId, MinorId};
+
+use crate::{
+    device::{Device, DeviceType},
+    fs::file::FileIo,
+    net::tun::TunFile,
+    prelude::*,
+};
+
+const TUN_MINOR: u32 = 200;
+
+/// The `/dev/net/tun` device.
+#[derive(Debug)]
+pub struct Tun {
+    id: DeviceId,
+}
+
+impl Tun {
+    pub fn new() -> Arc<Self> {
+        let major = super::MISC_MAJOR.get().unwrap().get();
+        let minor = MinorId::new(TUN_MINOR);
+
+        let id = DeviceId::new(major, minor);
+        Arc::new(Self { id })
+    }
+}
+
+impl Device for Tun {
+    fn type_(&self) -> DeviceType {
+        DeviceType::Char
+    }
+
+    fn id(&self) -> DeviceId {
+        self.id
+    }
+
+    fn devtmpfs_path(&self) -> Option<String> {
+        Some("net/tun".into())
+    }
+
+    fn class(&self) -> &'static str {
+        "misc"
+    }
+
+    fn open(&self) -> Result<Box<dyn FileIo>> {
+        Ok(Box::new(TunFile::new()))
+    }
+}
@@ -0,0 +1,437 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The watchdog device (`/dev/watchdog`).
+//!
+//! Opening the device starts the current watchdog timer, and writing to the device or issuing the
+//! `WDIOC_KEEPALIVE` ioctl pings it. Like Linux, closing the device stops the watchdog timer only
+//! if the magic character `V` has been written. Otherwise, the system will be reset once the
+//! timeout expires.
+//!
+//! If there are no hardware watchdog timers, a software watchdog timer (`softdog` in Linux) is
+//! registered, which restarts the system from a kernel timer.
+//!
+//! Reference:
+//! <https://elixir.bootlin.com/linux/v6.18/source/drivers/watchdog/watchdog_dev.c>
+//! <https://elixir.bootlin.com/linux/v6.18/source/drivers/watchdog/softdog.c>
+
+use core::{
+    ops::RangeInclusive,
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+    time::Duration,
+};
+
+use aster_watchdog::{AnyWatchdogDevice, WatchdogFlags};
+use device_id::{DeviceId, MinorId};
+use ostd::power::ExitCode;
+
+use crate::{
+    device::{Device, DeviceType},
+    events::IoEvents,
+    fs::{
+        file::{FileIo, StatusFlags},
+        vfs::inode::InodeIo,
+    },
+    prelude::*,
+    process::signal::{PollHandle, Pollable},
+    time::{
+        Timer,
+        clocks::MonotonicClock,
+        timer::{Timeout, TimerGuard},
+    },
+    util::ioctl::{RawIoctl, dispatch_ioctl},
+};
+
+/// The minor number of `/dev/watchdog`.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/linux/miscdevice.h>.
+const WATCHDOG_MINOR: u32 = 130;
+
+/// The `watchdog_info` structure.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/watchdog.h>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct WatchdogInfo {
+    options: u32,
+    firmware_version: u32,
+    identity: [u8; 32],
+}
+
+mod ioctl_defs {
+    use super::WatchdogInfo;
+    use crate::util::ioctl::{InOutData, OutData, ioc};
+
+    // Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/watchdog.h>
+
+    pub(super) type GetSupport    = ioc!(WDIOC_GETSUPPORT,    b'W', 0, OutData<WatchdogInfo>);
+    pub(super) type GetStatus     = ioc!(WDIOC_GETSTATUS,     b'W', 1, OutData<i32>);
+    pub(super) type GetBootStatus = ioc!(WDIOC_GETBOOTSTATUS, b'W', 2, OutData<i32>);
+    pub(super) type SetOptions    = ioc!(WDIOC_SETOPTIONS,    b'W', 4, OutData<i32>);
+    pub(super) type KeepAlive     = ioc!(WDIOC_KEEPALIVE,     b'W', 5, OutData<i32>);
+    pub(super) type SetTimeout    = ioc!(WDIOC_SETTIMEOUT,    b'W', 6, InOutData<i32>);
+    pub(super) type GetTimeout    = ioc!(WDIOC_GETTIMEOUT,    b'W', 7, OutData<i32>);
+    pub(super) type GetTimeLeft   = ioc!(WDIOC_GETTIMELEFT,   b'W', 10, OutData<i32>);
+}
+
+/// The option of `WDIOC_SETOPTIONS` that stops the watchdog timer.
+const WDIOS_DISABLECARD: i32 = 0x0001;
+/// The option of `WDIOC_SETOPTIONS` that starts the watchdog timer.
+const WDIOS_ENABLECARD: i32 = 0x0002;
+
+/// Whether `/dev/watchdog` is opened, since it can only be opened once at a time.
+static IS_OPEN: AtomicBool = AtomicBool::new(false);
+
+/// The `/dev/watchdog` device.
+#[derive(Debug)]
+pub struct Watchdog {
+    id: DeviceId,
+}
+
+impl Watchdog {
+    pub fn new() -> Arc<Self> {
+        let major = super::MISC_MAJOR.get().unwrap().get();
+        let minor = MinorId::new(WATCHDOG_MINOR);
+
+        let id = DeviceId::new(major, minor);
+        Arc::new(Self { id })
+    }
+}
+
+impl Device for Watchdog {
+    fn type_(&self) -> DeviceType {
+        DeviceType::Char
+    }
+
+    fn id(&self) -> DeviceId {
+        self.id
+    }
+
+    fn devtmpfs_path(&self) -> Option<String> {
+        Some("watchdog".into())
+    }
+
+    fn class(&self) -> &'static str {
+        "misc"
+    }
+
+    fn open(&self) -> Result<Box<dyn FileIo>> {
+        let Some((_, device)) = aster_watchdog::current_device() else {
+            return_errno_with_message!(Errno::ENODEV, "no watchdog timers exist");
+        };
+
+        if IS_OPEN.swap(true, Ordering::Acquire) {
+            return_errno_with_message!(Errno::EBUSY, "the watchdog device is already opened");
+        }
+
+        device.start();
+
+        Ok(Box::new(WatchdogFile {
+            device,
+            state: Mutex::new(WatchdogState {
+                is_active: true,
+                allows_release: false,
+                is_pinged: false,
+            }),
+        }))
+    }
+}
+
+struct WatchdogFile {
+    device: Arc<dyn AnyWatchdogDevice>,
+    state: Mutex<WatchdogState>,
+}
+
+struct WatchdogState {
+    /// Whether the watchdog timer is started.
+    is_active: bool,
+    /// Whether the magic character has been written in the last write.
+    allows_release: bool,
+    /// Whether the watchdog timer has been pinged since the last `WDIOC_GETSTATUS`.
+    is_pinged: bool,
+}
+
+impl WatchdogFile {
+    fn start(&self) {
+        let mut state = self.state.lock();
+
+        self.device.start();
+        state.is_active = true;
+    }
+
+    fn stop(&self) {
+        let mut state = self.state.lock();
+
+        self.device.stop();
+        state.is_active = false;
+    }
+
+    fn ping(&self) {
+        let mut state = self.state.lock();
+
+        state.is_pinged = true;
+        if state.is_active {
+            self.device.ping();
+        }
+    }
+
+    fn set_timeout(&self, timeout: i32) -> Result<()> {
+        let options = self.device.options();
+        if !options.contains(WatchdogFlags::SETTIMEOUT) {
+            return_errno_with_message!(Errno::EOPNOTSUPP, "the timeout cannot be set");
+        }
+
+        let timeout = u32::try_from(timeout)
+            .ok()
+            .filter(|timeout| self.device.timeout_range().contains(timeout))
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "the timeout is out of range"))?;
+
+        self.device.set_timeout(timeout);
+        // Like Linux, ping the watchdog timer so that the new timeout takes effect.
+        self.ping();
+
+        Ok(())
+    }
+
+    fn status(&self) -> WatchdogFlags {
+        let mut state = self.state.lock();
+
+        let mut status = WatchdogFlags::empty();
+        if state.allows_release {
+            status |= WatchdogFlags::MAGICCLOSE;
+        }
+        if core::mem::take(&mut state.is_pinged) {
+            status |= WatchdogFlags::KEEPALIVEPING;
+        }
+
+        status
+    }
+
+    fn info(&self) -> WatchdogInfo {
+        let mut identity = [0u8; 32];
+        let name = self.device.identity().as_bytes();
+        // Leave room for the null terminator.
+        let len = name.len().min(identity.len() - 1);
+        identity[..len].copy_from_slice(&name[..len]);
+
+        WatchdogInfo {
+            options: self.device.options().bits(),
+            firmware_version: 0,
+            identity,
+        }
+    }
+}
+
+impl Pollable for WatchdogFile {
+    fn poll(&self, mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
+        let events = IoEvents::OUT;
+        events & mask
+    }
+}
+
+impl InodeIo for WatchdogFile {
+    fn read_at(
+        &self,
+        _offset: usize,
+        _writer: &mut VmWriter,
+        _status_flags: StatusFlags,
+    ) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "the watchdog device is not readable")
+    }
+
+    fn write_at(
+        &self,
+        _offset: usize,
+        reader: &mut VmReader,
+        _status_flags: StatusFlags,
+    ) -> Result<usize> {
+        let len = reader.remain();
+        if len == 0 {
+            return Ok(0);
+        }
+
+        // Like Linux, only the last write decides whether the magic character has been written.
+        let mut allows_release = false;
+        while reader.has_remain() {
+            if reader.read_val::<u8>()? == b'V' {
+                allows_release = true;
+            }
+        }
+        self.state.lock().allows_release = allows_release;
+
+        self.ping();
+
+        Ok(len)
+    }
+}
+
+impl FileIo for WatchdogFile {
+    fn check_seekable(&self) -> Result<()> {
+        return_errno_with_message!(Errno::ESPIPE, "seek is not supported")
+    }
+
+    fn is_offset_aware(&self) -> bool {
+        false
+    }
+
+    fn ioctl(&self, raw_ioctl: RawIoctl) -> Result<i32> {
+        use ioctl_defs::*;
+
+        dispatch_ioctl!(match raw_ioctl {
+            cmd @ GetSupport => {
+                cmd.write(&self.info())?;
+            }
+            cmd @ GetStatus => {
+                cmd.write(&(self.status().bits() as i32))?;
+            }
+            cmd @ GetBootStatus => {
+                cmd.write(&(self.device.boot_status().bits() as i32))?;
+            }
+            cmd @ SetOptions => {
+                // Although declared as `_IOR`, the ioctl command reads the options from user space.
+                let options: i32 = current_userspace!().read_val(cmd.arg_addr())?;
+                if options & WDIOS_DISABLECARD != 0 {
+                    self.stop();
+                }
+                if options & WDIOS_ENABLECARD != 0 {
+                    self.start();
+                }
+            }
+            KeepAlive => {
+                if !self.device.options().contains(WatchdogFlags::KEEPALIVEPING) {
+                    return_errno_with_message!(
+                        Errno::EOPNOTSUPP,
+                        "the keepalive ping is not supported"
+                    );
+                }
+                self.ping();
+            }
+            cmd @ SetTimeout => {
+                self.set_timeout(cmd.read()?)?;
+                cmd.write(&(self.device.timeout() as i32))?;
+            }
+            cmd @ GetTimeout => {
+                cmd.write(&(self.device.timeout() as i32))?;
+            }
+            cmd @ GetTimeLeft => {
+                let Some(time_left) = self.device.time_left() else {
+                    return_errno_with_message!(
+                        Errno::EOPNOTSUPP,
+                        "the remaining time cannot be reported"
+                    );
+                };
+                cmd.write(&(time_left as i32))?;
+            }
+            _ => return_errno_with_message!(Errno::ENOTTY, "the ioctl command is unknown"),
+        });
+
+        Ok(0)
+    }
+}
+
+impl Drop for WatchdogFile {
+    fn drop(&mut self) {
+        let state = self.state.get_mut();
+
+        if state.is_active {
+            if state.allows_release || !self.device.options().contains(WatchdogFlags::MAGICCLOSE) {
+                self.device.stop();
+            } else {
+                error!("watchdog: unexpected close, not stopping the watchdog timer");
+                self.device.ping();
+            }
+        }
+
+        IS_OPEN.store(false, Ordering::Release);
+    }
+}
+
+/// The default timeout of the software watchdog timer in seconds.
+const SOFTDOG_DEFAULT_TIMEOUT: u32 = 60;
+/// The range of the timeout of the software watchdog timer in seconds.
+const SOFTDOG_TIMEOUT_RANGE: RangeInclusive<u32> = 1..=65535;
+
+/// A software watchdog timer.
+struct SoftDog {
+    timer: Arc<Timer>,
+    /// The timeout in seconds.
+    timeout: AtomicU32,
+    is_active: AtomicBool,
+}
+
+impl SoftDog {
+    fn new() -> Self {
+        let timer = MonotonicClock::timer_manager().create_timer(|_guard: TimerGuard| {
+            error!("softdog: initiating system reboot");
+            ostd::power::restart(ExitCode::Failure);
+        });
+
+        Self {
+            timer,
+            timeout: AtomicU32::new(SOFTDOG_DEFAULT_TIMEOUT),
+            is_active: AtomicBool::new(false),
+        }
+    }
+}
+
+impl AnyWatchdogDevice for SoftDog {
+    fn identity(&self) -> &str {
+        "Software Watchdog"
+    }
+
+    fn options(&self) -> WatchdogFlags {
+        WatchdogFlags::SETTIMEOUT | WatchdogFlags::KEEPALIVEPING | WatchdogFlags::MAGICCLOSE
+    }
+
+    fn start(&self) {
+        self.is_active.store(true, Ordering::Relaxed);
+        self.ping();
+    }
+
+    fn stop(&self) {
+        self.is_active.store(false, Ordering::Relaxed);
+        self.timer.lock().cancel();
+    }
+
+    fn ping(&self) {
+        let timeout = Duration::from_secs(self.timeout.load(Ordering::Relaxed) as u64);
+        self.timer.lock().set_timeout(Timeout::After(timeout));
+    }
+
+    fn timeout(&self) -> u32 {
+        self.timeout.load(Ordering::Relaxed)
+    }
+
+    fn timeout_range(&self) -> RangeInclusive<u32> {
+        SOFTDOG_TIMEOUT_RANGE
+    }
+
+    fn set_timeout(&self, timeout: u32) {
+        self.timeout.store(timeout, Ordering::Relaxed);
+    }
+
+    fn time_left(&self) -> Option<u32> {
+        if !self.is_active.load(Ordering::Relaxed) {
+            return Some(0);
+        }
+
+        Some(self.timer.lock().remain().as_secs() as u32)
+    }
+}
+
+impl Debug for SoftDog {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SoftDog")
+            .field("timeout", &self.timeout)
+            .field("is_active", &self.is_active)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Registers the software watchdog timer if there are no hardware watchdog timers.
+pub(super) fn init_softdog() {
+    if aster_watchdog::current_device().is_some() {
+        return;
+    }
+
+    aster_watchdog::register_device("softdog".into(), Arc::new(SoftDog::new()));
+}
@@ -1,27 +1,35 @@
 // SPDX-License-Identifier: MPL-2.0
 
+#[cfg(target_arch = "x86_64")]
+mod acpi;
+mod drm;
 mod evdev;
 mod fb;
+mod loop_device;
+mod mapper;
 mod mem;
 pub mod misc;
 mod pty;
 mod registry;
+mod rtc;
 mod shm;
+mod snd;
 pub mod tty;
+mod usb;
+mod virtio_port;
 
 use device_id::DeviceId;
-pub use mem::{getrandom, geturandom};
+pub use mem::{getrandom, geturandom, register_kmsg_poller};
 pub use pty::{PtyMaster, PtySlave, new_pty_pair};
 pub use registry::lookup;
+pub use rtc::notify_ntp_synced;
 
 use crate::{
     fs::{
-        file::{FileIo, InodeType, mkmod},
-        ramfs::RamFs,
-        vfs::{
-            inode::MknodType,
-            path::{FsPath, Path, PathResolver, PerMountFlags},
-        },
+        devtmpfs,
+        file::{FileIo, mkmod},
+        sysfs::{self, SysDevice},
+        vfs::{inode::MknodType, path::FsPath},
     },
     prelude::*,
 };
@@ -37,6 +45,11 @@ pub trait Device: Send + Sync + 'static {
     /// Returns the path where the device should appear in devtmpfs (usually under `/dev`), if any.
     fn devtmpfs_path(&self) -> Option<String>;
 
+    /// Returns the class of the device (e.g., `tty` or `input`).
+    ///
+    /// The device appears in sysfs under `/sys/class/<class>`.
+    fn class(&self) -> &'static str;
+
     /// Opens the device, returning a file-like object that the userspace can interact with by
     /// doing I/O.
     fn open(&self) -> Result<Box<dyn FileIo>>;
@@ -59,89 +72,122 @@ pub enum DeviceType {
     Block,
 }
 
-/// Adds a device node in `/dev`.
+/// Adds the device node of the device in devtmpfs, if the device has one.
 ///
-/// If the parent path does not exist, it will be created as a directory.
 /// This function should be called when registering a device.
-//
-// TODO: Figure out what should happen when unregistering the device.
-pub fn add_node(
-    dev_type: DeviceType,
-    dev_id: u64,
-    path: &str,
-    path_resolver: &PathResolver,
-) -> Result<Path> {
-    let mut dev_path = path_resolver.lookup(&FsPath::try_from("/dev").unwrap())?;
-    let mut relative_path = {
-        let relative_path = path.trim_start_matches('/');
-        if relative_path.is_empty() {
-            return_errno_with_message!(Errno::EINVAL, "the device path is invalid");
-        }
-        relative_path
+fn add_node(device: &dyn Device) {
+    let Some(devtmpfs_path) = device.devtmpfs_path() else {
+        return;
     };
 
-    while !relative_path.is_empty() {
-        let (next_name, path_remain) = if let Some((prefix, suffix)) = relative_path.split_once('/')
-        {
-            (prefix, suffix.trim_start_matches('/'))
-        } else {
-            (relative_path, "")
-        };
-
-        match path_resolver.lookup_at_path(&dev_path, next_name) {
-            Ok(next_path) => {
-                if path_remain.is_empty() {
-                    return_errno_with_message!(Errno::EEXIST, "the device node already exists");
-                }
-                dev_path = next_path;
-            }
-            Err(_) => {
-                if path_remain.is_empty() {
-                    // Create the device node
-                    let mknod_type = match dev_type {
-                        DeviceType::Block => MknodType::BlockDevice(dev_id),
-                        DeviceType::Char => MknodType::CharDevice(dev_id),
-                    };
-                    dev_path = dev_path.mknod(next_name, mkmod!(a+rw), mknod_type)?;
-                } else {
-                    // Create the parent directory
-                    dev_path =
-                        dev_path.new_fs_child(next_name, InodeType::Dir, mkmod!(a+rx, u+w))?;
-                }
-            }
-        }
-        relative_path = path_remain;
+    // Like Linux, failing to add the device node does not fail the device registration.
+    if let Err(err) = devtmpfs::add_node(&devtmpfs_path, mkmod!(a+rw), mknod_type(device)) {
+        warn!(
+            "failed to add the device node '{}': {:?}",
+            devtmpfs_path, err
+        );
     }
+}
 
-    Ok(dev_path)
+/// Removes the device node of the device from devtmpfs, if the device has one.
+///
+/// This function should be called when unregistering a device.
+fn remove_node(device: &dyn Device) {
+    let Some(devtmpfs_path) = device.devtmpfs_path() else {
+        return;
+    };
+
+    if let Err(err) = devtmpfs::remove_node(&devtmpfs_path, mknod_type(device)) {
+        warn!(
+            "failed to remove the device node '{}': {:?}",
+            devtmpfs_path, err
+        );
+    }
+}
+
+/// Adds the device to the device model in sysfs, if the device has a device node.
+///
+/// This function should be called when registering a device.
+fn add_sysfs_device(device: &dyn Device) {
+    let Some(devtmpfs_path) = device.devtmpfs_path() else {
+        return;
+    };
+
+    let sys_device = SysDevice {
+        class: device.class(),
+        name: sysfs_name(&devtmpfs_path).to_string(),
+        dev: Some((device.type_(), device.id())),
+        uevent_vars: vec![("DEVNAME", devtmpfs_path.clone())],
+        attrs: Vec::new(),
+    };
+    if let Err(err) = sysfs::add_device(sys_device, None) {
+        warn!(
+            "failed to add the device '{}' to sysfs: {:?}",
+            devtmpfs_path, err
+        );
+    }
+}
+
+/// Removes the device from the device model in sysfs, if the device has a device node.
+///
+/// This function should be called when unregistering a device.
+fn remove_sysfs_device(device: &dyn Device) {
+    let Some(devtmpfs_path) = device.devtmpfs_path() else {
+        return;
+    };
+
+    let name = sysfs_name(&devtmpfs_path);
+    let dev = Some((device.type_(), device.id()));
+    if let Err(err) = sysfs::remove_device(device.class(), name, dev, None) {
+        warn!(
+            "failed to remove the device '{}' from sysfs: {:?}",
+            devtmpfs_path, err
+        );
+    }
+}
+
+/// Returns the name of the device in sysfs, which is the last component of its devtmpfs path.
+fn sysfs_name(devtmpfs_path: &str) -> &str {
+    devtmpfs_path.rsplit('/').next().unwrap()
+}
+
+fn mknod_type(device: &dyn Device) -> MknodType {
+    let dev_id = device.id().as_encoded_u64();
+    match device.type_() {
+        DeviceType::Char => MknodType::CharDevice(dev_id),
+        DeviceType::Block => MknodType::BlockDevice(dev_id),
+    }
 }
 
 pub fn init_in_first_kthread() {
     registry::init_in_first_kthread();
+    loop_device::init_in_first_kthread();
+    mapper::init_in_first_kthread();
     mem::init_in_first_kthread();
     misc::init_in_first_kthread();
     evdev::init_in_first_kthread();
     fb::init_in_first_kthread();
+    rtc::init_in_first_kthread();
+    virtio_port::init_in_first_kthread();
+    drm::init_in_first_kthread();
+    snd::init_in_first_kthread();
+    usb::init_in_first_kthread();
+    #[cfg(target_arch = "x86_64")]
+    acpi::init_in_first_kthread();
 }
 
-/// Initializes the device nodes in devtmpfs after mounting rootfs.
+/// Mounts devtmpfs and initializes the remaining devices after mounting rootfs.
 pub fn init_in_first_process(ctx: &Context) -> Result<()> {
     let fs = ctx.thread_local.borrow_fs();
     let path_resolver = fs.resolver().read();
 
-    // Mount devtmpfs.
+    // Mount devtmpfs. The device nodes of the registered devices are already there.
     let dev_path = path_resolver.lookup(&FsPath::try_from("/dev")?)?;
-    dev_path.mount(
-        RamFs::new(),
-        PerMountFlags::default(),
-        Some("ramfs".to_string()),
-        ctx,
-    )?;
+    devtmpfs::mount_at(&dev_path, ctx)?;
 
     tty::init_in_first_process()?;
     pty::init_in_first_process(&path_resolver, ctx)?;
     shm::init_in_first_process(&path_resolver, ctx)?;
-    registry::init_in_first_process(&path_resolver)?;
 
     Ok(())
 }
@@ -209,6 +209,51 @@ impl TtyDriver for PtyDriver {
         }
     }
 
+    fn on_flow_change(&self, is_stopped: bool) {
+        // Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/tty/pty.c>.
+        let has_set = self.packet_ctrl.set_status(|packet_status| {
+            if is_stopped {
+                packet_status.remove(PacketStatus::START);
+                packet_status.insert(PacketStatus::STOP);
+            } else {
+                packet_status.remove(PacketStatus::STOP);
+                packet_status.insert(PacketStatus::START);
+            }
+        });
+
+        if has_set {
+            self.pollee
+                .notify(IoEvents::PRI | IoEvents::IN | IoEvents::RDNORM);
+        }
+    }
+
+    fn flush_output(&self) {
+        // Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/tty/pty.c>.
+        //
+        // The characters in the output buffer are considered to have been received by the master,
+        // so they are not discarded. Only the master in packet mode is notified.
+        let has_set = self.packet_ctrl.set_status(|packet_status| {
+            packet_status.insert(PacketStatus::FLUSHWRITE);
+        });
+
+        if has_set {
+            self.pollee
+                .notify(IoEvents::PRI | IoEvents::IN | IoEvents::RDNORM);
+        }
+    }
+
+    fn on_input_flush(&self) {
+        // Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/tty/n_tty.c>.
+        let has_set = self.packet_ctrl.set_status(|packet_status| {
+            packet_status.insert(PacketStatus::FLUSHREAD);
+        });
+
+        if has_set {
+            self.pollee
+                .notify(IoEvents::PRI | IoEvents::IN | IoEvents::RDNORM);
+        }
+    }
+
     fn ioctl(&self, tty: &Tty<Self>, raw_ioctl: RawIoctl) -> Result<bool>
     where
         Self: Sized,
@@ -254,5 +254,7 @@ impl Drop for PtyMaster {
 
         self.slave_flags().set_other_closed();
         self.slave.notify_hup();
+
+        (self.slave.clone() as Arc<dyn Terminal>).hang_up();
     }
 }
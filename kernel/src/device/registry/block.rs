@@ -1,19 +1,30 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use aster_block::{BlockDevice, SECTOR_SIZE};
-use aster_virtio::device::block::device::BlockDevice as VirtIoBlockDevice;
+use aster_block::{
+    BlockDevice, PartitionInfo, PartitionNode, SECTOR_SIZE, bio::BioStatus, id::Sid,
+    parse_partitions, request_queue::BioRequestQueue,
+};
+use aster_ahci::{AhciDisk, HotplugEvent};
+use aster_nvme::NvmeNamespace;
+use aster_usb::{DiskEvent, UsbDisk};
+use aster_virtio::device::{block::device::BlockDevice as VirtIoBlockDevice, scsi::device::ScsiDisk};
 use device_id::DeviceId;
 use ostd::mm::VmIo;
 
 use crate::{
-    device::{Device, DeviceType, add_node},
+    device::{Device, DeviceType, add_node, loop_device::LoopDevice, remove_node},
     events::IoEvents,
     fs::{
         file::{FileIo, StatusFlags},
-        vfs::{inode::InodeIo, path::PathResolver},
+        sysfs::{self, KObject, ShowFn, StoreFn, SysDevice},
+        vfs::inode::InodeIo,
     },
     prelude::*,
-    process::signal::{PollHandle, Pollable},
+    process::{
+        credentials::capabilities::CapSet,
+        posix_thread::AsPosixThread,
+        signal::{PollHandle, Pollable},
+    },
     thread::kernel_thread::ThreadOptions,
     util::ioctl::{RawIoctl, dispatch_ioctl},
 };
@@ -24,34 +35,334 @@ pub(super) fn init_in_first_kthread() {
             continue;
         }
 
-        let task_fn = move || {
-            info!("spawn the virt-io-block thread");
-            let virtio_block_device = device.downcast_ref::<VirtIoBlockDevice>().unwrap();
-            loop {
-                virtio_block_device.handle_requests();
+        // The requests must be handled before scanning the partitions.
+        if device.downcast_ref::<VirtIoBlockDevice>().is_some() {
+            let virtio_device = device.clone();
+            let task_fn = move || {
+                info!("spawn the virt-io-block thread");
+                let virtio_block_device =
+                    virtio_device.downcast_ref::<VirtIoBlockDevice>().unwrap();
+                loop {
+                    virtio_block_device.handle_requests();
+                }
+            };
+            ThreadOptions::new(task_fn).spawn();
+        } else if let Some(nvme_namespace) = device.downcast_ref::<NvmeNamespace>() {
+            // Spawn one thread for each I/O queue so that the requests can be submitted in
+            // parallel.
+            for queue_index in 0..nvme_namespace.num_io_queues() {
+                let nvme_device = device.clone();
+                let task_fn = move || {
+                    info!("spawn the NVMe thread for I/O queue {}", queue_index);
+                    let nvme_namespace = nvme_device.downcast_ref::<NvmeNamespace>().unwrap();
+                    loop {
+                        nvme_namespace.handle_requests(queue_index);
+                    }
+                };
+                ThreadOptions::new(task_fn).spawn();
             }
-        };
-        ThreadOptions::new(task_fn).spawn();
+        } else if device.downcast_ref::<ScsiDisk>().is_some() {
+            let scsi_device = device.clone();
+            let task_fn = move || {
+                info!("spawn the SCSI disk thread");
+                let scsi_disk = scsi_device.downcast_ref::<ScsiDisk>().unwrap();
+                loop {
+                    scsi_disk.handle_requests();
+                }
+            };
+            ThreadOptions::new(task_fn).spawn();
+        } else if device.downcast_ref::<AhciDisk>().is_some() {
+            spawn_ahci_disk_thread(device.clone());
+        }
+
+        add_disk(&device);
     }
-}
 
-pub(super) fn init_in_first_process(path_resolver: &PathResolver) -> Result<()> {
-    for device in aster_block::collect_all() {
-        let device = Arc::new(BlockFile::new(device));
-        if let Some(devtmpfs_path) = device.devtmpfs_path() {
-            let dev_id = device.id().as_encoded_u64();
-            add_node(DeviceType::Block, dev_id, &devtmpfs_path, path_resolver)?;
+    let task_fn = || {
+        info!("spawn the AHCI hot-plug thread");
+        loop {
+            match aster_ahci::wait_for_hotplug_event() {
+                HotplugEvent::Attached(disk) => {
+                    spawn_ahci_disk_thread(disk.clone());
+                    if let Err(err) = register(disk) {
+                        warn!("failed to register the AHCI disk: {:?}", err);
+                    }
+                }
+                HotplugEvent::Detached(disk) => {
+                    let _ = unregister(disk.id());
+                }
+            }
+        }
+    };
+    ThreadOptions::new(task_fn).spawn();
+
+    let task_fn = || {
+        info!("spawn the USB disk hot-plug thread");
+        loop {
+            match aster_usb::wait_for_disk_event() {
+                DiskEvent::Attached(disk) => {
+                    spawn_usb_disk_thread(disk.clone());
+                    if let Err(err) = register(disk) {
+                        warn!("failed to register the USB disk: {:?}", err);
+                    }
+                }
+                DiskEvent::Detached(disk) => {
+                    let _ = unregister(disk.id());
+                }
+            }
         }
+    };
+    ThreadOptions::new(task_fn).spawn();
+}
+
+/// Spawns the thread that handles the requests of the AHCI disk until it is detached.
+fn spawn_ahci_disk_thread(device: Arc<dyn BlockDevice>) {
+    let task_fn = move || {
+        info!("spawn the AHCI disk thread for {}", device.name());
+        let ahci_disk = device.downcast_ref::<AhciDisk>().unwrap();
+        while ahci_disk.handle_requests() {}
+    };
+    ThreadOptions::new(task_fn).spawn();
+}
+
+/// Spawns the thread that handles the requests of the USB disk until it is detached.
+fn spawn_usb_disk_thread(device: Arc<dyn BlockDevice>) {
+    let task_fn = move || {
+        info!("spawn the USB disk thread for {}", device.name());
+        let usb_disk = device.downcast_ref::<UsbDisk>().unwrap();
+        while usb_disk.handle_requests() {}
+    };
+    ThreadOptions::new(task_fn).spawn();
+}
+
+/// Registers a new block device after the boot.
+///
+/// The device node of the device will be added in devtmpfs. If the device is a disk, the device
+/// will be added to sysfs and its partitions will be scanned.
+pub fn register(device: Arc<dyn BlockDevice>) -> Result<()> {
+    if aster_block::register(device.clone()).is_err() {
+        return_errno_with_message!(Errno::EEXIST, "the block device already exists");
+    }
+
+    if device.is_partition() {
+        add_node(&BlockFile::new(device));
+    } else {
+        add_disk(&device);
     }
 
     Ok(())
 }
 
+/// Unregisters an existing block device, returning the device if found.
+///
+/// The device node of the device will be removed from devtmpfs. If the device is a disk, its
+/// partitions will be removed and the device will be removed from sysfs.
+pub fn unregister(id: DeviceId) -> Result<Arc<dyn BlockDevice>> {
+    if let Some(disk) = aster_block::lookup(id)
+        && !disk.is_partition()
+    {
+        update_partitions(&disk, Vec::new());
+    }
+
+    let Ok(device) = aster_block::unregister(id) else {
+        return_errno_with_message!(Errno::ENOENT, "the block device does not exist");
+    };
+    DEVICE_REGISTRY.lock().remove(&id.to_raw());
+
+    remove_node(&BlockFile::new(device.clone()));
+    if !device.is_partition() {
+        DISK_KOBJECTS.lock().remove(&id.to_raw());
+        if let Err(err) =
+            sysfs::remove_device("block", device.name(), Some((DeviceType::Block, id)), None)
+        {
+            warn!(
+                "failed to remove the disk '{}' from sysfs: {:?}",
+                device.name(),
+                err
+            );
+        }
+    }
+
+    Ok(device)
+}
+
+/// Re-reads the partition table of the disk and replaces its partitions.
+pub fn rescan_partitions(disk: &Arc<dyn BlockDevice>) {
+    let infos = if disk.can_scan_partitions() {
+        parse_partitions(disk).unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+
+    update_partitions(disk, infos);
+}
+
+/// Adds the device node of the disk, adds the disk to sysfs, and scans its partitions.
+fn add_disk(disk: &Arc<dyn BlockDevice>) {
+    add_node(&BlockFile::new(disk.clone()));
+
+    match sysfs::add_device(new_sys_device(disk, "disk"), None) {
+        Ok(kobject) => {
+            if disk.request_queue().is_some()
+                && let Err(err) = kobject.add_child(new_queue_kobject(disk))
+            {
+                warn!(
+                    "failed to add the request queue of the disk '{}' to sysfs: {:?}",
+                    disk.name(),
+                    err
+                );
+            }
+            DISK_KOBJECTS.lock().insert(disk.id().to_raw(), kobject);
+        }
+        Err(err) => warn!(
+            "failed to add the disk '{}' to sysfs: {:?}",
+            disk.name(),
+            err
+        ),
+    }
+
+    rescan_partitions(disk);
+}
+
+/// Replaces the partitions of the disk, updating devtmpfs and sysfs accordingly.
+fn update_partitions(disk: &Arc<dyn BlockDevice>, infos: Vec<Option<PartitionInfo>>) {
+    // Holding the lock also serializes the updates of the partitions.
+    let disk_kobjects = DISK_KOBJECTS.lock();
+    let disk_kobject = disk_kobjects.get(&disk.id().to_raw());
+
+    for partition in disk.partitions().unwrap_or_default() {
+        DEVICE_REGISTRY.lock().remove(&partition.id().to_raw());
+        remove_node(&BlockFile::new(partition.clone()));
+
+        let Some(disk_kobject) = disk_kobject else {
+            continue;
+        };
+        let dev = Some((DeviceType::Block, partition.id()));
+        if let Err(err) = sysfs::remove_device("block", partition.name(), dev, Some(disk_kobject))
+        {
+            warn!(
+                "failed to remove the partition '{}' from sysfs: {:?}",
+                partition.name(),
+                err
+            );
+        }
+    }
+
+    disk.set_partitions(infos);
+
+    for partition in disk.partitions().unwrap_or_default() {
+        add_node(&BlockFile::new(partition.clone()));
+
+        let Some(disk_kobject) = disk_kobject else {
+            continue;
+        };
+        let sys_device = new_sys_device(&partition, "partition");
+        if let Err(err) = sysfs::add_device(sys_device, Some(disk_kobject)) {
+            warn!(
+                "failed to add the partition '{}' to sysfs: {:?}",
+                partition.name(),
+                err
+            );
+        }
+    }
+}
+
+/// Describes a disk or a partition in sysfs.
+///
+/// Reference: <https://www.kernel.org/doc/Documentation/ABI/stable/sysfs-block>
+fn new_sys_device(device: &Arc<dyn BlockDevice>, dev_type: &str) -> SysDevice {
+    // The size may change (e.g., when a loop device is bound to a file).
+    let weak_device = Arc::downgrade(device);
+    let mut attrs = vec![
+        (
+            "size",
+            Box::new(move || {
+                let nr_sectors = weak_device
+                    .upgrade()
+                    .map_or(0, |device| device.metadata().nr_sectors);
+                format!("{}\n", nr_sectors)
+            }) as ShowFn,
+        ),
+        ("ro", Box::new(|| String::from("0\n")) as ShowFn),
+    ];
+    let mut uevent_vars = vec![
+        ("DEVNAME", device.name().to_string()),
+        ("DEVTYPE", dev_type.to_string()),
+    ];
+    if let Some(partition) = device.downcast_ref::<PartitionNode>() {
+        let number = partition.number().to_string();
+        let start = format!("{}\n", partition.info().start_sector());
+        uevent_vars.push(("PARTN", number.clone()));
+        attrs.push((
+            "partition",
+            Box::new(move || format!("{}\n", number)) as ShowFn,
+        ));
+        attrs.push(("start", Box::new(move || start.clone()) as ShowFn));
+    } else {
+        attrs.push(("removable", Box::new(|| String::from("0\n")) as ShowFn));
+    }
+
+    SysDevice {
+        class: "block",
+        name: device.name().to_string(),
+        dev: Some((DeviceType::Block, device.id())),
+        uevent_vars,
+        attrs,
+    }
+}
+
+/// Creates the `queue` directory of a disk in sysfs, which describes the request queue.
+///
+/// Reference: <https://www.kernel.org/doc/Documentation/ABI/stable/sysfs-block>
+fn new_queue_kobject(disk: &Arc<dyn BlockDevice>) -> Arc<KObject> {
+    let weak_disk = Arc::downgrade(disk);
+    let show_scheduler = Box::new(move || {
+        let Some(disk) = weak_disk.upgrade() else {
+            return String::new();
+        };
+        let current = disk.request_queue().unwrap().scheduler_name();
+        let mut schedulers = BioRequestQueue::available_schedulers()
+            .map(|name| {
+                if name == current {
+                    format!("[{}]", name)
+                } else {
+                    name.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        schedulers.push('\n');
+        schedulers
+    }) as ShowFn;
+
+    let weak_disk = Arc::downgrade(disk);
+    let store_scheduler = Box::new(move |value: &str| {
+        let disk = weak_disk.upgrade().ok_or(aster_systree::Error::IsDead)?;
+        disk.request_queue()
+            .unwrap()
+            .set_scheduler(value.trim())
+            .map_err(|_| aster_systree::Error::InvalidOperation)
+    }) as StoreFn;
+
+    let max_segments = format!("{}\n", disk.metadata().max_nr_segments_per_bio);
+
+    KObject::new_with_stores(
+        "queue",
+        vec![
+            ("scheduler", show_scheduler),
+            ("max_segments", Box::new(move || max_segments.clone()) as ShowFn),
+        ],
+        vec![("scheduler", store_scheduler)],
+    )
+}
+
 mod ioctl_defs {
-    use crate::util::ioctl::{OutData, ioc};
+    use crate::util::ioctl::{InData, NoData, OutData, ioc};
 
     // Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/fs.h>
+    pub(super) type BlkRrPart    = ioc!(BLKRRPART,    0x125F,        NoData);
     pub(super) type BlkGetSize64 = ioc!(BLKGETSIZE64, 0x12, 114, OutData<u64>);
+    pub(super) type BlkDiscard   = ioc!(BLKDISCARD,   0x1277,        InData<[u64; 2]>);
 }
 
 /// Represents a block device inode in the filesystem.
@@ -81,6 +392,10 @@ impl Device for BlockFile {
         Some(self.0.name().into())
     }
 
+    fn class(&self) -> &'static str {
+        "block"
+    }
+
     fn open(&self) -> Result<Box<dyn FileIo>> {
         Ok(Box::new(OpenBlockFile(self.0.clone())))
     }
@@ -139,19 +454,67 @@ impl FileIo for OpenBlockFile {
         use ioctl_defs::*;
 
         dispatch_ioctl!(match raw_ioctl {
+            BlkRrPart => {
+                let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+                if !credentials.effective_capset().contains(CapSet::SYS_ADMIN) {
+                    return_errno_with_message!(
+                        Errno::EACCES,
+                        "re-reading the partition table requires CAP_SYS_ADMIN"
+                    );
+                }
+                if !self.0.can_scan_partitions() {
+                    return_errno_with_message!(
+                        Errno::EINVAL,
+                        "the partition table of the block device cannot be scanned"
+                    );
+                }
+
+                rescan_partitions(&self.0);
+                Ok(0)
+            }
             cmd @ BlkGetSize64 => {
                 let size = (self.0.metadata().nr_sectors * SECTOR_SIZE) as u64;
                 cmd.write(&size)?;
                 Ok(0)
             }
-            _ => return_errno_with_message!(
-                Errno::ENOTTY,
-                "the ioctl command is not supported by block devices"
-            ),
+            cmd @ BlkDiscard => {
+                let [start, len] = cmd.read()?;
+                discard(self.0.as_ref(), start, len)?;
+                Ok(0)
+            }
+            _ => {
+                if let Some(loop_device) = self.0.downcast_ref::<LoopDevice>() {
+                    return loop_device.ioctl(raw_ioctl);
+                }
+                return_errno_with_message!(
+                    Errno::ENOTTY,
+                    "the ioctl command is not supported by block devices"
+                )
+            }
         })
     }
 }
 
+/// Discards the byte range of the block device.
+fn discard(device: &dyn BlockDevice, start: u64, len: u64) -> Result<()> {
+    if (start | len) % SECTOR_SIZE as u64 != 0 {
+        return_errno_with_message!(Errno::EINVAL, "the range is not aligned to sectors");
+    }
+    let device_size = (device.metadata().nr_sectors * SECTOR_SIZE) as u64;
+    if start.checked_add(len).is_none_or(|end| end > device_size) {
+        return_errno_with_message!(Errno::EINVAL, "the range exceeds the block device");
+    }
+
+    let sid_range = Sid::from_offset(start as usize)..Sid::from_offset((start + len) as usize);
+    match device.discard(sid_range)? {
+        BioStatus::Complete => Ok(()),
+        BioStatus::NotSupported => {
+            return_errno_with_message!(Errno::EOPNOTSUPP, "the block device cannot discard")
+        }
+        err_status => Err(Error::from(err_status)),
+    }
+}
+
 pub(super) fn lookup(id: DeviceId) -> Option<Arc<dyn Device>> {
     let block_device = aster_block::lookup(id)?;
 
@@ -163,6 +526,9 @@ pub(super) fn lookup(id: DeviceId) -> Option<Arc<dyn Device>> {
     Some(block_device_file)
 }
 
+/// The kernel objects of the disks in sysfs, to which the partitions are added.
+static DISK_KOBJECTS: Mutex<BTreeMap<u32, Arc<KObject>>> = Mutex::new(BTreeMap::new());
+
 // TODO: Merge the two mapping tables, one is here and the other is in the block component.
 // Maintaining two mapping tables is undesirable due to duplication and (potential) inconsistency.
 static DEVICE_REGISTRY: Mutex<BTreeMap<u32, Arc<dyn Device>>> = Mutex::new(BTreeMap::new());
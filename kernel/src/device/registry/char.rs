@@ -7,31 +7,44 @@ use core::ops::Range;
 use device_id::{DeviceId, MajorId};
 
 use crate::{
-    device::{Device, DeviceType, add_node},
-    fs::vfs::path::PathResolver,
+    device::{Device, add_node, add_sysfs_device, remove_node, remove_sysfs_device},
     prelude::*,
 };
 
 static DEVICE_REGISTRY: Mutex<BTreeMap<u32, Arc<dyn Device>>> = Mutex::new(BTreeMap::new());
 
 /// Registers a new char device.
+///
+/// The device node of the device will be added in devtmpfs, and the device will be added to sysfs.
 pub fn register(device: Arc<dyn Device>) -> Result<()> {
     let mut registry = DEVICE_REGISTRY.lock();
     let id = device.id().to_raw();
     if registry.contains_key(&id) {
         return_errno_with_message!(Errno::EEXIST, "the char device already exists");
     }
-    registry.insert(id, device);
+    registry.insert(id, device.clone());
+    drop(registry);
+
+    add_node(device.as_ref());
+    add_sysfs_device(device.as_ref());
 
     Ok(())
 }
 
 /// Unregisters an existing char device, returning the device if found.
+///
+/// The device node of the device will be removed from devtmpfs, and the device will be removed
+/// from sysfs.
 pub fn unregister(id: DeviceId) -> Result<Arc<dyn Device>> {
-    DEVICE_REGISTRY
+    let device = DEVICE_REGISTRY
         .lock()
         .remove(&id.to_raw())
-        .ok_or_else(|| Error::with_message(Errno::ENOENT, "the char device does not exist"))
+        .ok_or_else(|| Error::with_message(Errno::ENOENT, "the char device does not exist"))?;
+
+    remove_sysfs_device(device.as_ref());
+    remove_node(device.as_ref());
+
+    Ok(device)
 }
 
 /// Collects all char devices.
@@ -76,7 +89,6 @@ pub fn acquire_major(major: MajorId) -> Result<MajorIdOwner> {
 ///
 /// The returned `MajorIdOwner` object represents the ownership to the major ID.
 /// Until the object is dropped, this major ID cannot be acquired via `acquire_major` or `allocate_major` again.
-#[expect(dead_code)]
 pub fn allocate_major() -> Result<MajorIdOwner> {
     let mut majors = MAJORS.lock();
 
@@ -109,14 +121,3 @@ impl Drop for MajorIdOwner {
         MAJORS.lock().remove(&self.0.get());
     }
 }
-
-pub(super) fn init_in_first_process(path_resolver: &PathResolver) -> Result<()> {
-    for device in collect_all() {
-        if let Some(devtmpfs_path) = device.devtmpfs_path() {
-            let dev_id = device.id().as_encoded_u64();
-            add_node(DeviceType::Char, dev_id, &devtmpfs_path, path_resolver)?;
-        }
-    }
-
-    Ok(())
-}
@@ -0,0 +1,544 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The real-time clock (RTC) device (`/dev/rtc0`).
+//!
+//! The device reads and sets the time of the hardware RTC and manages its alarm. When the alarm
+//! fires, the event can be read from the device.
+//!
+//! Like Linux, the system time is initialized from the RTC at boot. Besides, a kernel thread
+//! writes the system time back to the RTC periodically if the system time is synchronized by NTP.
+//!
+//! Reference:
+//! <https://elixir.bootlin.com/linux/v6.18/source/drivers/rtc/dev.c>
+//! <https://elixir.bootlin.com/linux/v6.18/source/drivers/rtc/interface.c>
+
+use core::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use aster_time::RTC_ALARM_HANDLER_FN;
+use device_id::{DeviceId, MinorId};
+use ostd::sync::WaitQueue;
+use spin::Once;
+use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time};
+
+use super::{
+    Device, DeviceType,
+    registry::char::{self, MajorIdOwner},
+};
+use crate::{
+    events::IoEvents,
+    fs::{
+        file::{FileIo, StatusFlags},
+        vfs::inode::InodeIo,
+    },
+    prelude::*,
+    process::{
+        credentials::capabilities::CapSet,
+        signal::{PollHandle, Pollable, Pollee},
+    },
+    thread::kernel_thread::ThreadOptions,
+    time::{clocks::RealTimeClock, ntp},
+    util::ioctl::{RawIoctl, dispatch_ioctl},
+};
+
+/// The `rtc_time` structure.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/rtc.h>.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, Pod)]
+struct RtcTime {
+    tm_sec: i32,
+    tm_min: i32,
+    tm_hour: i32,
+    tm_mday: i32,
+    tm_mon: i32,
+    tm_year: i32,
+    tm_wday: i32,
+    tm_yday: i32,
+    tm_isdst: i32,
+}
+
+/// The `rtc_wkalrm` structure.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/rtc.h>.
+#[repr(C)]
+#[padding_struct]
+#[derive(Debug, Default, Clone, Copy, Pod)]
+struct RtcWkAlrm {
+    enabled: u8,
+    pending: u8,
+    time: RtcTime,
+}
+
+mod ioctl_defs {
+    use super::{RtcTime, RtcWkAlrm};
+    use crate::util::ioctl::{InData, NoData, OutData, ioc};
+
+    // Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/rtc.h>
+
+    pub(super) type EnableAlarmIrq  = ioc!(RTC_AIE_ON,    b'p', 0x01, NoData);
+    pub(super) type DisableAlarmIrq = ioc!(RTC_AIE_OFF,   b'p', 0x02, NoData);
+    pub(super) type SetAlarm        = ioc!(RTC_ALM_SET,   b'p', 0x07, InData<RtcTime>);
+    pub(super) type ReadAlarm       = ioc!(RTC_ALM_READ,  b'p', 0x08, OutData<RtcTime>);
+    pub(super) type ReadTime        = ioc!(RTC_RD_TIME,   b'p', 0x09, OutData<RtcTime>);
+    pub(super) type SetTime         = ioc!(RTC_SET_TIME,  b'p', 0x0a, InData<RtcTime>);
+    pub(super) type SetWakeAlarm    = ioc!(RTC_WKALM_SET, b'p', 0x0f, InData<RtcWkAlrm>);
+    pub(super) type ReadWakeAlarm   = ioc!(RTC_WKALM_RD,  b'p', 0x10, OutData<RtcWkAlrm>);
+}
+
+/// The flag in the interrupt data indicating that an interrupt has occurred.
+const RTC_IRQF: usize = 0x80;
+/// The flag in the interrupt data indicating that the alarm has fired.
+const RTC_AF: usize = 0x20;
+
+/// The interval between two writes of the system time to the RTC.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/kernel/time/ntp.c>.
+const SYNC_INTERVAL: Duration = Duration::from_secs(11 * 60);
+
+static RTC_MAJOR: Once<MajorIdOwner> = Once::new();
+
+static RTC: Once<Arc<Rtc>> = Once::new();
+
+/// The wait queue of the kernel thread that writes the system time to the RTC.
+static SYNC_WAIT_QUEUE: WaitQueue = WaitQueue::new();
+
+/// The `/dev/rtc0` device.
+struct Rtc {
+    id: DeviceId,
+    /// Whether the device is opened, since it can only be opened once at a time.
+    is_open: AtomicBool,
+    alarm: SpinLock<Alarm>,
+    /// The interrupt data (`irq_data` in Linux).
+    ///
+    /// The lower 8 bits are the interrupt flags and the remaining bits are the number of
+    /// interrupts since the last read.
+    irq_data: SpinLock<usize>,
+    pollee: Pollee,
+}
+
+#[derive(Debug)]
+struct Alarm {
+    time: PrimitiveDateTime,
+    enabled: bool,
+}
+
+impl Rtc {
+    fn new() -> Self {
+        let major = RTC_MAJOR.get().unwrap().get();
+        let minor = MinorId::new(0);
+
+        let epoch = OffsetDateTime::UNIX_EPOCH;
+
+        Self {
+            id: DeviceId::new(major, minor),
+            is_open: AtomicBool::new(false),
+            alarm: SpinLock::new(Alarm {
+                time: PrimitiveDateTime::new(epoch.date(), epoch.time()),
+                enabled: false,
+            }),
+            irq_data: SpinLock::new(0),
+            pollee: Pollee::new(),
+        }
+    }
+
+    fn read_time(&self) -> Result<PrimitiveDateTime> {
+        from_rtc_system_time(&aster_time::read_rtc()?)
+    }
+
+    fn set_time(&self, time: PrimitiveDateTime) -> Result<()> {
+        aster_time::set_rtc(&to_rtc_system_time(time))?;
+        Ok(())
+    }
+
+    fn read_alarm(&self) -> RtcWkAlrm {
+        let alarm = self.alarm.disable_irq().lock();
+
+        RtcWkAlrm {
+            enabled: alarm.enabled as u8,
+            pending: 0,
+            time: RtcTime::from_date_time(alarm.time),
+            ..Default::default()
+        }
+    }
+
+    fn set_alarm(&self, time: PrimitiveDateTime, enabled: bool) -> Result<()> {
+        let mut alarm = self.alarm.disable_irq().lock();
+
+        alarm.enabled = self.program_alarm(time, enabled)?;
+        alarm.time = time;
+
+        Ok(())
+    }
+
+    fn enable_alarm(&self, enabled: bool) -> Result<()> {
+        let mut alarm = self.alarm.disable_irq().lock();
+
+        if alarm.enabled == enabled {
+            return Ok(());
+        }
+
+        alarm.enabled = self.program_alarm(alarm.time, enabled)?;
+
+        Ok(())
+    }
+
+    /// Programs the alarm of the hardware RTC.
+    ///
+    /// Like Linux, if the alarm is enabled but its time has already passed, the alarm fires
+    /// immediately instead of being programmed.
+    ///
+    /// This method returns whether the alarm is still enabled.
+    fn program_alarm(&self, time: PrimitiveDateTime, enabled: bool) -> Result<bool> {
+        if !enabled {
+            aster_time::set_rtc_alarm(None)?;
+            return Ok(false);
+        }
+
+        if time <= self.read_time()? {
+            aster_time::set_rtc_alarm(None)?;
+            self.add_alarm_event();
+            return Ok(false);
+        }
+
+        aster_time::set_rtc_alarm(Some(&to_rtc_system_time(time)))?;
+        Ok(true)
+    }
+
+    /// Handles the alarm that has fired.
+    fn on_alarm(&self) {
+        self.alarm.disable_irq().lock().enabled = false;
+        self.add_alarm_event();
+    }
+
+    fn add_alarm_event(&self) {
+        let mut irq_data = self.irq_data.disable_irq().lock();
+        *irq_data = (*irq_data + (1 << 8)) | RTC_IRQF | RTC_AF;
+        drop(irq_data);
+
+        self.pollee.notify(IoEvents::IN | IoEvents::RDNORM);
+    }
+
+    fn take_irq_data(&self) -> Result<usize> {
+        let mut irq_data = self.irq_data.disable_irq().lock();
+        if *irq_data == 0 {
+            return_errno_with_message!(Errno::EAGAIN, "no interrupts have occurred");
+        }
+
+        self.pollee.invalidate();
+        Ok(core::mem::take(&mut *irq_data))
+    }
+
+    fn check_io_events(&self) -> IoEvents {
+        if *self.irq_data.disable_irq().lock() != 0 {
+            IoEvents::IN | IoEvents::RDNORM
+        } else {
+            IoEvents::empty()
+        }
+    }
+}
+
+impl Device for Rtc {
+    fn type_(&self) -> DeviceType {
+        DeviceType::Char
+    }
+
+    fn id(&self) -> DeviceId {
+        self.id
+    }
+
+    fn devtmpfs_path(&self) -> Option<String> {
+        Some("rtc0".into())
+    }
+
+    fn class(&self) -> &'static str {
+        "rtc"
+    }
+
+    fn open(&self) -> Result<Box<dyn FileIo>> {
+        if self.is_open.swap(true, Ordering::Acquire) {
+            return_errno_with_message!(Errno::EBUSY, "the RTC device is already opened");
+        }
+
+        Ok(Box::new(RtcFile {
+            rtc: RTC.get().unwrap(),
+        }))
+    }
+}
+
+impl Debug for Rtc {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Rtc")
+            .field("id", &self.id)
+            .field("alarm", &self.alarm)
+            .finish_non_exhaustive()
+    }
+}
+
+struct RtcFile {
+    rtc: &'static Rtc,
+}
+
+impl Pollable for RtcFile {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.rtc
+            .pollee
+            .poll_with(mask, poller, || self.rtc.check_io_events())
+    }
+}
+
+impl InodeIo for RtcFile {
+    fn read_at(
+        &self,
+        _offset: usize,
+        writer: &mut VmWriter,
+        status_flags: StatusFlags,
+    ) -> Result<usize> {
+        let len = writer.avail();
+        if len != size_of::<u32>() && len < size_of::<usize>() {
+            return_errno_with_message!(Errno::EINVAL, "the buffer size is invalid");
+        }
+
+        let irq_data = if status_flags.contains(StatusFlags::O_NONBLOCK) {
+            self.rtc.take_irq_data()?
+        } else {
+            self.wait_events(IoEvents::IN, None, || self.rtc.take_irq_data())?
+        };
+
+        if len == size_of::<u32>() {
+            writer.write_val(&(irq_data as u32))?;
+            Ok(size_of::<u32>())
+        } else {
+            writer.write_val(&irq_data)?;
+            Ok(size_of::<usize>())
+        }
+    }
+
+    fn write_at(
+        &self,
+        _offset: usize,
+        _reader: &mut VmReader,
+        _status_flags: StatusFlags,
+    ) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "the RTC device is not writable")
+    }
+}
+
+impl FileIo for RtcFile {
+    fn check_seekable(&self) -> Result<()> {
+        return_errno_with_message!(Errno::ESPIPE, "seek is not supported")
+    }
+
+    fn is_offset_aware(&self) -> bool {
+        false
+    }
+
+    fn ioctl(&self, raw_ioctl: RawIoctl) -> Result<i32> {
+        use ioctl_defs::*;
+
+        dispatch_ioctl!(match raw_ioctl {
+            cmd @ ReadTime => {
+                let time = self.rtc.read_time()?;
+                cmd.write(&RtcTime::from_date_time(time))?;
+            }
+            cmd @ SetTime => {
+                let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+                if !credentials.effective_capset().contains(CapSet::SYS_TIME) {
+                    return_errno_with_message!(
+                        Errno::EACCES,
+                        "setting the RTC time requires CAP_SYS_TIME"
+                    );
+                }
+
+                let time = cmd.read()?.to_date_time()?;
+                self.rtc.set_time(time)?;
+            }
+            cmd @ ReadAlarm => {
+                cmd.write(&self.rtc.read_alarm().time)?;
+            }
+            cmd @ SetAlarm => {
+                let time = self.handle_set_alarm(&cmd.read()?)?;
+                self.rtc.set_alarm(time, false)?;
+            }
+            cmd @ ReadWakeAlarm => {
+                cmd.write(&self.rtc.read_alarm())?;
+            }
+            cmd @ SetWakeAlarm => {
+                let wake_alarm = cmd.read()?;
+                let time = wake_alarm.time.to_date_time()?;
+                self.rtc.set_alarm(time, wake_alarm.enabled != 0)?;
+            }
+            EnableAlarmIrq => {
+                self.rtc.enable_alarm(true)?;
+            }
+            DisableAlarmIrq => {
+                self.rtc.enable_alarm(false)?;
+            }
+            _ => return_errno_with_message!(Errno::ENOTTY, "the ioctl command is unknown"),
+        });
+
+        Ok(0)
+    }
+}
+
+impl RtcFile {
+    /// Computes the alarm time for the [`ioctl_defs::SetAlarm`] ioctl command.
+    ///
+    /// Only the hour, the minute, and the second are specified. Like Linux, the alarm is set
+    /// within the next 24 hours.
+    fn handle_set_alarm(&self, alarm_time: &RtcTime) -> Result<PrimitiveDateTime> {
+        let now = self.rtc.read_time()?;
+
+        let alarm_time = RtcTime {
+            tm_mday: now.day() as i32,
+            tm_mon: u8::from(now.month()) as i32 - 1,
+            tm_year: now.year() - 1900,
+            ..*alarm_time
+        };
+        let alarm = alarm_time.to_date_time()?;
+
+        if alarm >= now {
+            return Ok(alarm);
+        }
+        alarm
+            .checked_add(time::Duration::DAY)
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "the alarm time is invalid"))
+    }
+}
+
+impl Drop for RtcFile {
+    fn drop(&mut self) {
+        self.rtc.is_open.store(false, Ordering::Release);
+    }
+}
+
+impl RtcTime {
+    /// Converts the RTC time to a date and time.
+    ///
+    /// Like `rtc_valid_tm` in Linux, the time must be valid and not be earlier than 1970.
+    fn to_date_time(self) -> Result<PrimitiveDateTime> {
+        let invalid = || Error::with_message(Errno::EINVAL, "the RTC time is invalid");
+
+        if self.tm_year < 70 {
+            return Err(invalid());
+        }
+        let year = self.tm_year.checked_add(1900).ok_or_else(invalid)?;
+        let month = self
+            .tm_mon
+            .checked_add(1)
+            .and_then(|month| u8::try_from(month).ok())
+            .and_then(|month| Month::try_from(month).ok())
+            .ok_or_else(invalid)?;
+        let day = u8::try_from(self.tm_mday).map_err(|_| invalid())?;
+        let date = Date::from_calendar_date(year, month, day).map_err(|_| invalid())?;
+
+        let hour = u8::try_from(self.tm_hour).map_err(|_| invalid())?;
+        let minute = u8::try_from(self.tm_min).map_err(|_| invalid())?;
+        let second = u8::try_from(self.tm_sec).map_err(|_| invalid())?;
+        let time = Time::from_hms(hour, minute, second).map_err(|_| invalid())?;
+
+        Ok(PrimitiveDateTime::new(date, time))
+    }
+
+    fn from_date_time(time: PrimitiveDateTime) -> Self {
+        Self {
+            tm_sec: time.second() as i32,
+            tm_min: time.minute() as i32,
+            tm_hour: time.hour() as i32,
+            tm_mday: time.day() as i32,
+            tm_mon: u8::from(time.month()) as i32 - 1,
+            tm_year: time.year() - 1900,
+            tm_wday: time.weekday().number_days_from_sunday() as i32,
+            tm_yday: time.ordinal() as i32 - 1,
+            tm_isdst: 0,
+        }
+    }
+}
+
+fn to_rtc_system_time(time: PrimitiveDateTime) -> aster_time::SystemTime {
+    aster_time::SystemTime {
+        year: time.year() as u16,
+        month: u8::from(time.month()),
+        day: time.day(),
+        hour: time.hour(),
+        minute: time.minute(),
+        second: time.second(),
+        nanos: time.nanosecond() as u64,
+    }
+}
+
+fn from_rtc_system_time(rtc_time: &aster_time::SystemTime) -> Result<PrimitiveDateTime> {
+    let invalid = || Error::with_message(Errno::EIO, "the RTC reports an invalid time");
+
+    let month = Month::try_from(rtc_time.month).map_err(|_| invalid())?;
+    let date = Date::from_calendar_date(rtc_time.year as i32, month, rtc_time.day)
+        .map_err(|_| invalid())?;
+    let time =
+        Time::from_hms(rtc_time.hour, rtc_time.minute, rtc_time.second).map_err(|_| invalid())?;
+
+    Ok(PrimitiveDateTime::new(date, time))
+}
+
+/// Notifies that the system time has been synchronized by NTP.
+///
+/// The system time will be written back to the RTC periodically.
+pub fn notify_ntp_synced() {
+    SYNC_WAIT_QUEUE.wake_all();
+}
+
+/// Writes the system time to the RTC.
+fn sync_rtc_clock() -> Result<()> {
+    let now = RealTimeClock::get().read_time();
+    let time = OffsetDateTime::from_unix_timestamp(now.as_secs() as i64)
+        .map_err(|_| Error::with_message(Errno::EINVAL, "the system time is out of range"))?;
+
+    aster_time::set_rtc(&to_rtc_system_time(PrimitiveDateTime::new(
+        time.date(),
+        time.time(),
+    )))?;
+    Ok(())
+}
+
+/// Spawns the kernel thread that writes the system time to the RTC.
+///
+/// Like Linux, the system time is written every 11 minutes while it is synchronized by NTP.
+fn spawn_sync_thread() {
+    let task_fn = || {
+        loop {
+            if !ntp::is_synced() {
+                SYNC_WAIT_QUEUE.wait_until(|| ntp::is_synced().then_some(()));
+            }
+
+            if let Err(err) = sync_rtc_clock() {
+                warn!("failed to write the system time to the RTC: {:?}", err);
+            }
+
+            let _ =
+                SYNC_WAIT_QUEUE.wait_until_or_timeout(|| -> Option<()> { None }, &SYNC_INTERVAL);
+        }
+    };
+
+    ThreadOptions::new(task_fn).spawn();
+}
+
+fn handle_alarm() {
+    if let Some(rtc) = RTC.get() {
+        rtc.on_alarm();
+    }
+}
+
+pub(super) fn init_in_first_kthread() {
+    if !aster_time::has_rtc() {
+        return;
+    }
+
+    RTC_MAJOR.call_once(|| char::allocate_major().unwrap());
+    let rtc = RTC.call_once(|| Arc::new(Rtc::new()));
+    char::register(rtc.clone()).unwrap();
+
+    RTC_ALARM_HANDLER_FN.call_once(|| handle_alarm);
+
+    spawn_sync_thread();
+}
@@ -0,0 +1,262 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Control devices (`/dev/snd/controlC<card>`).
+
+use alloc::format;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use aster_virtio::device::sound::PcmDirection;
+use device_id::{DeviceId, MinorId};
+
+use super::{
+    SND_MAJOR,
+    pcm::{PcmDevice, SndPcmInfo, copy_str},
+};
+use crate::{
+    current_userspace,
+    device::{Device, DeviceType},
+    events::IoEvents,
+    fs::{
+        file::{FileIo, StatusFlags},
+        vfs::inode::InodeIo,
+    },
+    prelude::*,
+    process::signal::{PollHandle, Pollable, Pollee},
+    util::ioctl::{RawIoctl, dispatch_ioctl},
+};
+
+/// The version of the control interface, which is `SNDRV_CTL_VERSION` in Linux.
+const SNDRV_CTL_VERSION: i32 = (2 << 16) | 9;
+
+/// `struct snd_ctl_card_info` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct SndCtlCardInfo {
+    card: i32,
+    pad: i32,
+    id: [u8; 16],
+    driver: [u8; 16],
+    name: [u8; 32],
+    longname: [u8; 80],
+    reserved_: [u8; 16],
+    mixername: [u8; 80],
+    components: [u8; 128],
+}
+
+/// `struct snd_ctl_elem_list` in Linux.
+#[repr(C)]
+#[padding_struct]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct SndCtlElemList {
+    offset: u32,
+    space: u32,
+    used: u32,
+    count: u32,
+    pids: u64,
+    reserved: [u8; 50],
+}
+
+pub(super) struct ControlDevice {
+    id: DeviceId,
+    /// The index of the sound card
+    card: u32,
+    pcms: Vec<Arc<PcmDevice>>,
+    weak_self: Weak<Self>,
+}
+
+impl ControlDevice {
+    pub(super) fn new(card: u32, pcms: Vec<Arc<PcmDevice>>) -> Arc<Self> {
+        let id = DeviceId::new(SND_MAJOR.get().unwrap().get(), MinorId::new(card * 32));
+
+        Arc::new_cyclic(|weak_self| Self {
+            id,
+            card,
+            pcms,
+            weak_self: weak_self.clone(),
+        })
+    }
+
+    fn card_info(&self) -> SndCtlCardInfo {
+        let mut info = SndCtlCardInfo::new_zeroed();
+        info.card = self.card as i32;
+        copy_str(&mut info.id, "SoundCard");
+        copy_str(&mut info.driver, "virtio-snd");
+        copy_str(&mut info.name, "VirtIO SoundCard");
+        copy_str(
+            &mut info.longname,
+            &format!("VirtIO SoundCard at virtio sound device {}", self.card),
+        );
+        copy_str(&mut info.mixername, "virtio-snd");
+        info
+    }
+
+    /// Returns the number of the next PCM device after `device`, or -1 if there is none.
+    fn next_pcm_device(&self, device: i32) -> i32 {
+        self.pcms
+            .iter()
+            .map(|pcm| pcm.device() as i32)
+            .filter(|pcm_device| *pcm_device > device)
+            .min()
+            .unwrap_or(-1)
+    }
+
+    fn pcm_info(&self, info: &mut SndPcmInfo) -> Result<()> {
+        let direction = match info.stream {
+            0 => PcmDirection::Output,
+            1 => PcmDirection::Input,
+            _ => return_errno_with_message!(Errno::EINVAL, "the stream direction is invalid"),
+        };
+
+        let Some(pcm) = self
+            .pcms
+            .iter()
+            .find(|pcm| pcm.device() == info.device && pcm.direction() == direction)
+        else {
+            return_errno_with_message!(Errno::ENXIO, "the PCM device does not exist");
+        };
+        if info.subdevice != 0 {
+            return_errno_with_message!(Errno::ENXIO, "the PCM subdevice does not exist");
+        }
+
+        pcm.fill_info(info);
+
+        Ok(())
+    }
+}
+
+impl Device for ControlDevice {
+    fn type_(&self) -> DeviceType {
+        DeviceType::Char
+    }
+
+    fn id(&self) -> DeviceId {
+        self.id
+    }
+
+    fn devtmpfs_path(&self) -> Option<String> {
+        Some(format!("snd/controlC{}", self.card))
+    }
+
+    fn class(&self) -> &'static str {
+        "sound"
+    }
+
+    fn open(&self) -> Result<Box<dyn FileIo>> {
+        let device = self.weak_self.upgrade().unwrap();
+        Ok(Box::new(ControlFile {
+            device,
+            is_subscribed: AtomicBool::new(false),
+            pollee: Pollee::new(),
+        }))
+    }
+}
+
+struct ControlFile {
+    device: Arc<ControlDevice>,
+    is_subscribed: AtomicBool,
+    pollee: Pollee,
+}
+
+impl ControlFile {
+    fn try_read(&self) -> Result<usize> {
+        if !self.is_subscribed.load(Ordering::Relaxed) {
+            return_errno_with_message!(Errno::EBADFD, "the events are not subscribed");
+        }
+
+        // There are no control elements, so there will be no events.
+        return_errno_with_message!(Errno::EAGAIN, "there are no events");
+    }
+}
+
+impl Pollable for ControlFile {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.pollee.poll_with(mask, poller, IoEvents::empty)
+    }
+}
+
+impl InodeIo for ControlFile {
+    fn read_at(
+        &self,
+        _offset: usize,
+        _writer: &mut VmWriter,
+        status_flags: StatusFlags,
+    ) -> Result<usize> {
+        if status_flags.contains(StatusFlags::O_NONBLOCK) {
+            self.try_read()
+        } else {
+            self.wait_events(IoEvents::IN, None, || self.try_read())
+        }
+    }
+
+    fn write_at(
+        &self,
+        _offset: usize,
+        _reader: &mut VmReader,
+        _status_flags: StatusFlags,
+    ) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "control devices cannot be written");
+    }
+}
+
+impl FileIo for ControlFile {
+    fn check_seekable(&self) -> Result<()> {
+        return_errno_with_message!(Errno::ESPIPE, "the inode is a control device");
+    }
+
+    fn is_offset_aware(&self) -> bool {
+        false
+    }
+
+    fn ioctl(&self, raw_ioctl: RawIoctl) -> Result<i32> {
+        use super::ioctl_defs::*;
+
+        dispatch_ioctl!(match raw_ioctl {
+            cmd @ GetPversion => {
+                cmd.write(&SNDRV_CTL_VERSION)?;
+            }
+            cmd @ GetCardInfo => {
+                cmd.write(&self.device.card_info())?;
+            }
+            cmd @ GetElemList => {
+                let mut list = cmd.read()?;
+                list.used = 0;
+                list.count = 0;
+                cmd.write(&list)?;
+            }
+            cmd @ SubscribeEvents => {
+                let subscribe = cmd.read()?;
+                if subscribe < 0 {
+                    let is_subscribed = self.is_subscribed.load(Ordering::Relaxed);
+                    cmd.write(&(is_subscribed as i32))?;
+                } else {
+                    self.is_subscribed.store(subscribe > 0, Ordering::Relaxed);
+                }
+            }
+            cmd @ PcmNextDevice => {
+                let device: i32 = current_userspace!().read_val(cmd.arg_addr())?;
+                cmd.write(&self.device.next_pcm_device(device))?;
+            }
+            cmd @ GetPcmInfo => {
+                let mut info = cmd.read()?;
+                self.device.pcm_info(&mut info)?;
+                cmd.write(&info)?;
+            }
+            cmd @ PcmPreferSubdevice => {
+                // There is only one subdevice in each PCM device.
+                let _subdevice = cmd.read()?;
+            }
+            cmd @ GetPowerState => {
+                const SNDRV_CTL_POWER_D0: i32 = 0;
+                cmd.write(&SNDRV_CTL_POWER_D0)?;
+            }
+            _ => {
+                return_errno_with_message!(
+                    Errno::ENOTTY,
+                    "the ioctl command is not supported by control devices"
+                );
+            }
+        });
+
+        Ok(0)
+    }
+}
@@ -0,0 +1,29 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::{
+    control::{SndCtlCardInfo, SndCtlElemList},
+    pcm::SndPcmInfo,
+};
+use crate::util::ioctl::{InData, InOutData, OutData, ioc};
+
+// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/sound/asound.h>
+
+pub(super) type GetPversion =
+    ioc!(SNDRV_CTL_IOCTL_PVERSION,             b'U', 0x00, OutData<i32>);
+pub(super) type GetCardInfo =
+    ioc!(SNDRV_CTL_IOCTL_CARD_INFO,            b'U', 0x01, OutData<SndCtlCardInfo>);
+pub(super) type GetElemList =
+    ioc!(SNDRV_CTL_IOCTL_ELEM_LIST,            b'U', 0x10, InOutData<SndCtlElemList>);
+pub(super) type SubscribeEvents =
+    ioc!(SNDRV_CTL_IOCTL_SUBSCRIBE_EVENTS,     b'U', 0x16, InOutData<i32>);
+
+// Linux declares `SNDRV_CTL_IOCTL_PCM_NEXT_DEVICE` with `_IOR`, but the device is an input.
+pub(super) type PcmNextDevice =
+    ioc!(SNDRV_CTL_IOCTL_PCM_NEXT_DEVICE,      b'U', 0x30, OutData<i32>);
+pub(super) type GetPcmInfo =
+    ioc!(SNDRV_CTL_IOCTL_PCM_INFO,             b'U', 0x31, InOutData<SndPcmInfo>);
+pub(super) type PcmPreferSubdevice =
+    ioc!(SNDRV_CTL_IOCTL_PCM_PREFER_SUBDEVICE, b'U', 0x32, InData<i32>);
+
+pub(super) type GetPowerState =
+    ioc!(SNDRV_CTL_IOCTL_POWER_STATE,          b'U', 0xd1, OutData<i32>);
@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Sound devices of the Advanced Linux Sound Architecture (ALSA) (`/dev/snd/*`).
+//!
+//! Each virtio sound device is exposed as a sound card, which consists of a control device and
+//! the PCM devices for its PCM streams. The control device supports enumerating the PCM devices,
+//! but it has no control elements (e.g., mixers or jacks).
+//!
+//! Reference: <https://elixir.bootlin.com/linux/v6.18/source/sound/virtio/virtio_card.c>.
+
+mod control;
+mod ioctl_defs;
+mod pcm;
+
+use aster_virtio::device::sound::{self, device::SoundDevice};
+use device_id::MajorId;
+use spin::Once;
+
+use self::{control::ControlDevice, pcm::PcmDevice};
+use super::registry::char::{self, MajorIdOwner};
+use crate::prelude::*;
+
+/// The major device number of sound devices.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/major.h>.
+const SND_MAJOR_ID: u16 = 116;
+
+/// The maximum number of sound cards.
+///
+/// Like Linux, each card owns 32 minor numbers, so there can be 8 cards at most.
+const MAX_CARDS: u32 = 8;
+
+/// The maximum number of PCM devices of each direction in a sound card.
+const MAX_PCM_DEVICES: u32 = 8;
+
+static SND_MAJOR: Once<MajorIdOwner> = Once::new();
+
+pub(super) fn init_in_first_kthread() {
+    SND_MAJOR.call_once(|| char::acquire_major(MajorId::new(SND_MAJOR_ID)).unwrap());
+
+    for (index, sound) in sound::all_devices().into_iter().enumerate() {
+        let card = index as u32;
+        if card >= MAX_CARDS {
+            warn!("too many virtio sound devices, the remaining ones are ignored");
+            break;
+        }
+
+        if let Err(err) = add_card(sound, card) {
+            warn!(
+                "failed to add the sound card for the virtio sound device: {:?}",
+                err
+            );
+        }
+    }
+}
+
+fn add_card(sound: Arc<SoundDevice>, card: u32) -> Result<()> {
+    let infos = sound.query_pcm_infos()?;
+
+    // Like Linux, the streams of the same function node form a PCM device, whose playback and
+    // capture substreams are the first output and input streams, respectively.
+    let mut nids = Vec::new();
+    let mut pcms: Vec<Arc<PcmDevice>> = Vec::new();
+    for info in infos {
+        let device = match nids.iter().position(|nid| *nid == info.nid) {
+            Some(device) => device,
+            None => {
+                nids.push(info.nid);
+                nids.len() - 1
+            }
+        } as u32;
+        if device >= MAX_PCM_DEVICES {
+            warn!(
+                "too many PCM devices, the stream {} is ignored",
+                info.stream_id
+            );
+            continue;
+        }
+
+        let direction = info.direction;
+        if pcms
+            .iter()
+            .any(|pcm| pcm.device() == device && pcm.direction() == direction)
+        {
+            warn!(
+                "multiple substreams are not supported, the stream {} is ignored",
+                info.stream_id
+            );
+            continue;
+        }
+
+        let Some(pcm) = PcmDevice::new(sound.clone(), info, card, device) else {
+            warn!("the stream {} has no usable configurations", info.stream_id);
+            continue;
+        };
+        pcms.push(pcm);
+    }
+
+    for pcm in pcms.iter() {
+        char::register(pcm.clone())?;
+    }
+
+    let control = ControlDevice::new(card, pcms);
+    char::register(control)
+}
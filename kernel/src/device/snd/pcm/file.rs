@@ -0,0 +1,541 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use aster_virtio::device::sound::PcmDirection;
+
+use super::{
+    PcmDevice, SndPcmInfo,
+    hw_params::{SNDRV_PCM_ACCESS_MMAP_INTERLEAVED, SndPcmHwParams},
+    runtime::{PcmState, SwConfig, TstampType},
+};
+use crate::{
+    current_userspace,
+    events::IoEvents,
+    fs::{
+        file::{FileIo, Mappable, StatusFlags},
+        vfs::inode::InodeIo,
+    },
+    prelude::*,
+    process::signal::{PollHandle, Pollable},
+    time::timespec_t,
+    util::ioctl::{RawIoctl, dispatch_ioctl},
+};
+
+/// The version of the PCM interface, which is `SNDRV_PCM_VERSION` in Linux.
+const SNDRV_PCM_VERSION: i32 = (2 << 16) | 18;
+
+/// `struct snd_pcm_sw_params` in Linux.
+#[repr(C)]
+#[padding_struct]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct SndPcmSwParams {
+    tstamp_mode: i32,
+    period_step: u32,
+    sleep_min: u32,
+    avail_min: u64,
+    xfer_align: u64,
+    start_threshold: u64,
+    stop_threshold: u64,
+    silence_threshold: u64,
+    silence_size: u64,
+    boundary: u64,
+    proto: u32,
+    tstamp_type: u32,
+    reserved: [u8; 56],
+}
+
+/// `struct snd_pcm_status` in Linux.
+#[repr(C)]
+#[padding_struct]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct SndPcmStatus {
+    state: i32,
+    trigger_tstamp: timespec_t,
+    tstamp: timespec_t,
+    appl_ptr: u64,
+    hw_ptr: u64,
+    delay: i64,
+    avail: u64,
+    avail_max: u64,
+    overrange: u64,
+    suspended_state: i32,
+    audio_tstamp_data: u32,
+    audio_tstamp: timespec_t,
+    driver_tstamp: timespec_t,
+    audio_tstamp_accuracy: u32,
+    reserved: [u8; 20],
+}
+
+/// `struct snd_pcm_mmap_status` in Linux.
+#[repr(C)]
+#[padding_struct]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct SndPcmMmapStatus {
+    state: i32,
+    pad1: i32,
+    hw_ptr: u64,
+    tstamp: timespec_t,
+    suspended_state: i32,
+    audio_tstamp: timespec_t,
+}
+
+/// `struct snd_pcm_mmap_control` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct SndPcmMmapControl {
+    appl_ptr: u64,
+    avail_min: u64,
+}
+
+/// `struct snd_pcm_sync_ptr` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct SndPcmSyncPtr {
+    flags: u32,
+    pad1: u32,
+    status: SndPcmMmapStatus,
+    status_reserved: [u8; 64 - size_of::<SndPcmMmapStatus>()],
+    control: SndPcmMmapControl,
+    control_reserved: [u8; 64 - size_of::<SndPcmMmapControl>()],
+}
+
+/// `struct snd_xferi` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct SndXferi {
+    result: i64,
+    buf: u64,
+    frames: u64,
+}
+
+/// `struct snd_pcm_channel_info` in Linux.
+#[repr(C)]
+#[padding_struct]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct SndPcmChannelInfo {
+    channel: u32,
+    offset: i64,
+    first: u32,
+    step: u32,
+}
+
+const SNDRV_PCM_TSTAMP_ENABLE: i32 = 1;
+
+const SNDRV_PCM_SYNC_PTR_HWSYNC: u32 = 1 << 0;
+const SNDRV_PCM_SYNC_PTR_APPL: u32 = 1 << 1;
+const SNDRV_PCM_SYNC_PTR_AVAIL_MIN: u32 = 1 << 2;
+
+pub(super) struct PcmFile {
+    device: Arc<PcmDevice>,
+}
+
+impl PcmFile {
+    pub(super) fn new(device: Arc<PcmDevice>) -> Self {
+        Self { device }
+    }
+
+    fn handle_hw_params(&self, params: &mut SndPcmHwParams) -> Result<()> {
+        let config = params.choose(&self.device.hw)?;
+        self.device.runtime.lock().set_hw_params(config)?;
+        self.device.pollee.notify(IoEvents::IN | IoEvents::OUT);
+
+        Ok(())
+    }
+
+    fn handle_sw_params(&self, sw_params: &mut SndPcmSwParams) -> Result<()> {
+        if sw_params.tstamp_mode > SNDRV_PCM_TSTAMP_ENABLE || sw_params.tstamp_mode < 0 {
+            return_errno_with_message!(Errno::EINVAL, "the timestamp mode is invalid");
+        }
+        let tstamp_type = TstampType::try_from(sw_params.tstamp_type as i32)
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the timestamp type is invalid"))?;
+
+        let mut runtime = self.device.runtime.lock();
+        // Filling the ring buffer with silence is not supported, so the silence threshold and
+        // size are ignored.
+        sw_params.boundary = runtime.set_sw_params(SwConfig {
+            avail_min: sw_params.avail_min as usize,
+            start_threshold: sw_params.start_threshold as usize,
+            stop_threshold: sw_params.stop_threshold as usize,
+        })? as u64;
+        runtime.set_tstamp_type(tstamp_type);
+        drop(runtime);
+
+        self.device.pollee.invalidate();
+
+        Ok(())
+    }
+
+    fn get_status(&self) -> SndPcmStatus {
+        let status = self.device.runtime.lock().status();
+
+        let mut raw = SndPcmStatus::new_zeroed();
+        raw.state = status.state as i32;
+        raw.trigger_tstamp = timespec_t::from(status.trigger_tstamp);
+        raw.tstamp = timespec_t::from(status.tstamp);
+        raw.appl_ptr = status.appl_ptr as u64;
+        raw.hw_ptr = status.hw_ptr as u64;
+        raw.delay = status.delay as i64;
+        raw.avail = status.avail as u64;
+        raw.avail_max = status.avail as u64;
+        raw.suspended_state = PcmState::Open as i32;
+        raw.driver_tstamp = raw.tstamp;
+        raw
+    }
+
+    fn sync_ptr(&self, sync_ptr: &mut SndPcmSyncPtr) -> Result<()> {
+        if sync_ptr.flags & SNDRV_PCM_SYNC_PTR_HWSYNC != 0 {
+            self.hwsync()?;
+        }
+
+        let mut runtime = self.device.runtime.lock();
+        if runtime.state() != PcmState::Open {
+            if sync_ptr.flags & SNDRV_PCM_SYNC_PTR_APPL == 0 {
+                runtime.set_appl_ptr(sync_ptr.control.appl_ptr as usize)?;
+            }
+            if sync_ptr.flags & SNDRV_PCM_SYNC_PTR_AVAIL_MIN == 0 {
+                runtime.set_avail_min(sync_ptr.control.avail_min as usize)?;
+            }
+        }
+        let status = runtime.status();
+        drop(runtime);
+
+        self.device.pollee.invalidate();
+
+        sync_ptr.status.state = status.state as i32;
+        sync_ptr.status.hw_ptr = status.hw_ptr as u64;
+        sync_ptr.status.tstamp = timespec_t::from(status.tstamp);
+        sync_ptr.status.suspended_state = PcmState::Open as i32;
+        sync_ptr.status.audio_tstamp = timespec_t::from(Duration::ZERO);
+        sync_ptr.control.appl_ptr = status.appl_ptr as u64;
+        sync_ptr.control.avail_min = status.avail_min as u64;
+
+        Ok(())
+    }
+
+    /// Checks the state of the stream, which is what `SNDRV_PCM_IOCTL_HWSYNC` does.
+    ///
+    /// The hardware pointer is always up to date, since it is moved when the I/O messages are
+    /// completed.
+    fn hwsync(&self) -> Result<()> {
+        match self.device.runtime.lock().state() {
+            PcmState::Xrun => return_errno_with_message!(Errno::EPIPE, "an xrun occurs"),
+            PcmState::Open | PcmState::Setup => {
+                return_errno_with_message!(Errno::EBADFD, "the PCM stream is not prepared")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn get_channel_info(&self, info: &mut SndPcmChannelInfo) -> Result<()> {
+        let runtime = self.device.runtime.lock();
+        let Some(config) = runtime.config() else {
+            return_errno_with_message!(Errno::EBADFD, "the hardware parameters are not set");
+        };
+        if info.channel >= config.channels {
+            return_errno_with_message!(Errno::EINVAL, "the channel does not exist");
+        }
+
+        // The frames are interleaved in the ring buffer.
+        let frame_bits = (config.frame_bytes * 8) as u32;
+        info.offset = 0;
+        info.first = info.channel * (frame_bits / config.channels);
+        info.step = frame_bits;
+
+        Ok(())
+    }
+
+    fn drain(&self) -> Result<()> {
+        self.device.runtime.lock().start_drain()?;
+        self.device.pollee.notify(IoEvents::IN | IoEvents::OUT);
+
+        self.wait_events(IoEvents::OUT, None, || {
+            if self.device.runtime.lock().is_draining() {
+                return_errno_with_message!(Errno::EAGAIN, "the PCM stream is draining");
+            }
+            Ok(())
+        })
+    }
+
+    /// Writes the frames from the user space, blocking until all of them are written.
+    ///
+    /// On success, the number of the written frames is returned.
+    fn write_frames(&self, reader: &mut VmReader, is_nonblocking: bool) -> Result<usize> {
+        self.check_direction(PcmDirection::Output)?;
+
+        let mut total = 0;
+        while reader.has_remain() {
+            let result = if is_nonblocking {
+                self.device.runtime.lock().write_frames(reader)
+            } else {
+                self.wait_events(IoEvents::OUT, None, || {
+                    self.device.runtime.lock().write_frames(reader)
+                })
+            };
+            self.device.pollee.invalidate();
+
+            match result {
+                Ok(0) => break,
+                Ok(frames) => total += frames,
+                Err(_) if total > 0 => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(total)
+    }
+
+    /// Reads the frames to the user space, blocking until all of them are read.
+    ///
+    /// On success, the number of the read frames is returned.
+    fn read_frames(&self, writer: &mut VmWriter, is_nonblocking: bool) -> Result<usize> {
+        self.check_direction(PcmDirection::Input)?;
+
+        let mut total = 0;
+        while writer.has_avail() {
+            let result = if is_nonblocking {
+                self.device.runtime.lock().read_frames(writer)
+            } else {
+                self.wait_events(IoEvents::IN, None, || {
+                    self.device.runtime.lock().read_frames(writer)
+                })
+            };
+            self.device.pollee.invalidate();
+
+            match result {
+                Ok(0) => break,
+                Ok(frames) => total += frames,
+                Err(_) if total > 0 => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(total)
+    }
+
+    fn check_direction(&self, direction: PcmDirection) -> Result<()> {
+        if self.device.direction() != direction {
+            return_errno_with_message!(Errno::EINVAL, "the transfer direction is wrong");
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of bytes of a frame.
+    fn frame_bytes(&self) -> Result<usize> {
+        self.device
+            .runtime
+            .lock()
+            .config()
+            .map(|config| config.frame_bytes)
+            .ok_or_else(|| {
+                Error::with_message(Errno::EBADFD, "the hardware parameters are not set")
+            })
+    }
+
+    fn check_frame_aligned(&self, len: usize) -> Result<usize> {
+        let frame_bytes = self.frame_bytes()?;
+        if !len.is_multiple_of(frame_bytes) {
+            return_errno_with_message!(Errno::EINVAL, "the length is not a multiple of frames");
+        }
+
+        Ok(frame_bytes)
+    }
+
+    /// Notifies the waiters after the state is changed by an ioctl command.
+    fn notify_state_changed(&self) {
+        self.device.pollee.notify(IoEvents::IN | IoEvents::OUT);
+    }
+}
+
+impl Pollable for PcmFile {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.device
+            .pollee
+            .poll_with(mask, poller, || self.device.runtime.lock().io_events())
+    }
+}
+
+impl InodeIo for PcmFile {
+    fn read_at(
+        &self,
+        _offset: usize,
+        writer: &mut VmWriter,
+        status_flags: StatusFlags,
+    ) -> Result<usize> {
+        let frame_bytes = self.check_frame_aligned(writer.avail())?;
+        let frames = self.read_frames(writer, status_flags.contains(StatusFlags::O_NONBLOCK))?;
+        Ok(frames * frame_bytes)
+    }
+
+    fn write_at(
+        &self,
+        _offset: usize,
+        reader: &mut VmReader,
+        status_flags: StatusFlags,
+    ) -> Result<usize> {
+        let frame_bytes = self.check_frame_aligned(reader.remain())?;
+        let frames = self.write_frames(reader, status_flags.contains(StatusFlags::O_NONBLOCK))?;
+        Ok(frames * frame_bytes)
+    }
+}
+
+impl FileIo for PcmFile {
+    fn check_seekable(&self) -> Result<()> {
+        return_errno_with_message!(Errno::ESPIPE, "the inode is a PCM device");
+    }
+
+    fn is_offset_aware(&self) -> bool {
+        false
+    }
+
+    fn mappable(&self) -> Result<Mappable> {
+        // Only the ring buffer (at offset zero) can be mapped. Failing to map the status and the
+        // control records before the hardware parameters are set makes `alsa-lib` fall back to
+        // `SNDRV_PCM_IOCTL_SYNC_PTR`, which is what we want.
+        let runtime = self.device.runtime.lock();
+        let Some(vmo) = runtime.vmo() else {
+            return_errno_with_message!(Errno::ENXIO, "the hardware parameters are not set");
+        };
+        if runtime.config().unwrap().access != SNDRV_PCM_ACCESS_MMAP_INTERLEAVED {
+            return_errno_with_message!(Errno::EINVAL, "the access type is not mmap");
+        }
+
+        Ok(Mappable::Vmo(vmo.clone()))
+    }
+
+    fn ioctl(&self, raw_ioctl: RawIoctl) -> Result<i32> {
+        use super::ioctl_defs::*;
+
+        dispatch_ioctl!(match raw_ioctl {
+            cmd @ GetPversion => {
+                cmd.write(&SNDRV_PCM_VERSION)?;
+            }
+            cmd @ GetInfo => {
+                let mut info = SndPcmInfo::new_zeroed();
+                self.device.fill_info(&mut info);
+                cmd.write(&info)?;
+            }
+            cmd @ SetTstamp => {
+                let tstamp_mode = cmd.read()?;
+                if !(0..=SNDRV_PCM_TSTAMP_ENABLE).contains(&tstamp_mode) {
+                    return_errno_with_message!(Errno::EINVAL, "the timestamp mode is invalid");
+                }
+            }
+            cmd @ SetTtstamp => {
+                let tstamp_type = TstampType::try_from(cmd.read()?).map_err(|_| {
+                    Error::with_message(Errno::EINVAL, "the timestamp type is invalid")
+                })?;
+                self.device.runtime.lock().set_tstamp_type(tstamp_type);
+            }
+            cmd @ SetUserPversion => {
+                // The layouts of the structures are the same for all the versions on 64-bit
+                // platforms, so the version of the user space does not matter.
+                let _user_pversion = cmd.read()?;
+            }
+            cmd @ HwRefine => {
+                let mut params = cmd.read()?;
+                params.refine(&self.device.hw)?;
+                cmd.write(&params)?;
+            }
+            cmd @ HwParams => {
+                let mut params = cmd.read()?;
+                self.handle_hw_params(&mut params)?;
+                cmd.write(&params)?;
+            }
+            HwFree => {
+                self.device.runtime.lock().free_hw_params()?;
+                self.notify_state_changed();
+            }
+            cmd @ SwParams => {
+                let mut sw_params = cmd.read()?;
+                self.handle_sw_params(&mut sw_params)?;
+                cmd.write(&sw_params)?;
+            }
+            cmd @ GetStatus => {
+                cmd.write(&self.get_status())?;
+            }
+            cmd @ GetStatusExt => {
+                // Only the default audio timestamp is supported, so the input is ignored.
+                cmd.write(&self.get_status())?;
+            }
+            cmd @ GetDelay => {
+                self.hwsync()?;
+                let delay = self.device.runtime.lock().status().delay;
+                cmd.write(&(delay as i64))?;
+            }
+            HwSync => {
+                self.hwsync()?;
+            }
+            cmd @ SyncPtr => {
+                let mut sync_ptr = cmd.read()?;
+                self.sync_ptr(&mut sync_ptr)?;
+                cmd.write(&sync_ptr)?;
+            }
+            cmd @ GetChannelInfo => {
+                let mut info: SndPcmChannelInfo = current_userspace!().read_val(cmd.arg_addr())?;
+                self.get_channel_info(&mut info)?;
+                cmd.write(&info)?;
+            }
+            Prepare => {
+                self.device.runtime.lock().prepare()?;
+                self.notify_state_changed();
+            }
+            Reset => {
+                self.device.runtime.lock().reset()?;
+                self.notify_state_changed();
+            }
+            Start => {
+                self.device.runtime.lock().start()?;
+                self.notify_state_changed();
+            }
+            DropFrames => {
+                self.device.runtime.lock().drop_frames()?;
+                self.notify_state_changed();
+            }
+            Drain => {
+                self.drain()?;
+            }
+            cmd @ Pause => {
+                self.device.runtime.lock().pause(cmd.get() != 0)?;
+                self.notify_state_changed();
+            }
+            // FIXME: The status flags are not available here, so the transfer ioctls always
+            // block as if `O_NONBLOCK` were not set.
+            cmd @ WriteiFrames => {
+                let mut xferi = cmd.read()?;
+                let frame_bytes = self.frame_bytes()?;
+                let len = (xferi.frames as usize)
+                    .checked_mul(frame_bytes)
+                    .ok_or_else(|| Error::with_message(Errno::EINVAL, "too many frames"))?;
+                let mut reader = current_userspace!().reader(xferi.buf as Vaddr, len)?;
+                xferi.result = self.write_frames(&mut reader, false)? as i64;
+                current_userspace!().write_val(cmd.arg_addr(), &xferi.result)?;
+            }
+            cmd @ ReadiFrames => {
+                let mut xferi: SndXferi = current_userspace!().read_val(cmd.arg_addr())?;
+                let frame_bytes = self.frame_bytes()?;
+                let len = (xferi.frames as usize)
+                    .checked_mul(frame_bytes)
+                    .ok_or_else(|| Error::with_message(Errno::EINVAL, "too many frames"))?;
+                let mut writer = current_userspace!().writer(xferi.buf as Vaddr, len)?;
+                xferi.result = self.read_frames(&mut writer, false)? as i64;
+                cmd.write(&xferi)?;
+            }
+            _ => {
+                return_errno_with_message!(
+                    Errno::ENOTTY,
+                    "the ioctl command is not supported by PCM devices"
+                );
+            }
+        });
+
+        Ok(0)
+    }
+}
+
+impl Drop for PcmFile {
+    fn drop(&mut self) {
+        self.device.close();
+    }
+}
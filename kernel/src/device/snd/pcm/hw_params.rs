@@ -0,0 +1,883 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The hardware parameters of PCM streams.
+//!
+//! The user space narrows down the configuration space step by step with
+//! `SNDRV_PCM_IOCTL_HW_REFINE`, so every change must be propagated through the relations between
+//! the parameters (e.g., `buffer_bytes = buffer_size * frame_bits / 8`). Both the relations and
+//! the interval arithmetic follow those in Linux.
+//!
+//! Reference: <https://elixir.bootlin.com/linux/v6.18/source/sound/core/pcm_native.c>,
+//! <https://elixir.bootlin.com/linux/v6.18/source/sound/core/pcm_lib.c>.
+
+use aster_virtio::device::sound::{PcmFormat, PcmInfo, PcmRate};
+
+use crate::prelude::*;
+
+// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/sound/asound.h>
+
+const SNDRV_PCM_HW_PARAM_ACCESS: usize = 0;
+const SNDRV_PCM_HW_PARAM_FORMAT: usize = 1;
+const SNDRV_PCM_HW_PARAM_SUBFORMAT: usize = 2;
+const SNDRV_PCM_HW_PARAM_SAMPLE_BITS: usize = 8;
+const SNDRV_PCM_HW_PARAM_FRAME_BITS: usize = 9;
+const SNDRV_PCM_HW_PARAM_CHANNELS: usize = 10;
+const SNDRV_PCM_HW_PARAM_RATE: usize = 11;
+const SNDRV_PCM_HW_PARAM_PERIOD_TIME: usize = 12;
+const SNDRV_PCM_HW_PARAM_PERIOD_SIZE: usize = 13;
+const SNDRV_PCM_HW_PARAM_PERIOD_BYTES: usize = 14;
+const SNDRV_PCM_HW_PARAM_PERIODS: usize = 15;
+const SNDRV_PCM_HW_PARAM_BUFFER_TIME: usize = 16;
+const SNDRV_PCM_HW_PARAM_BUFFER_SIZE: usize = 17;
+const SNDRV_PCM_HW_PARAM_BUFFER_BYTES: usize = 18;
+
+const FIRST_INTERVAL: usize = SNDRV_PCM_HW_PARAM_SAMPLE_BITS;
+const NR_MASKS: usize = 3;
+const NR_INTERVALS: usize = 12;
+
+pub(super) const SNDRV_PCM_ACCESS_MMAP_INTERLEAVED: u32 = 0;
+pub(super) const SNDRV_PCM_ACCESS_RW_INTERLEAVED: u32 = 3;
+
+const SNDRV_PCM_SUBFORMAT_STD: u32 = 0;
+
+const SNDRV_PCM_INFO_MMAP: u32 = 0x0000_0001;
+const SNDRV_PCM_INFO_MMAP_VALID: u32 = 0x0000_0002;
+const SNDRV_PCM_INFO_BATCH: u32 = 0x0000_0010;
+const SNDRV_PCM_INFO_INTERLEAVED: u32 = 0x0000_0100;
+const SNDRV_PCM_INFO_BLOCK_TRANSFER: u32 = 0x0001_0000;
+const SNDRV_PCM_INFO_PAUSE: u32 = 0x0008_0000;
+
+/// The capabilities of the PCM streams.
+///
+/// The hardware pointer moves forward only when a period is completed by the device, so the
+/// streams are reported as `SNDRV_PCM_INFO_BATCH`.
+pub(super) const PCM_INFO: u32 = SNDRV_PCM_INFO_MMAP
+    | SNDRV_PCM_INFO_MMAP_VALID
+    | SNDRV_PCM_INFO_BATCH
+    | SNDRV_PCM_INFO_INTERLEAVED
+    | SNDRV_PCM_INFO_BLOCK_TRANSFER
+    | SNDRV_PCM_INFO_PAUSE;
+
+const PERIOD_BYTES_MIN: u32 = 64;
+const PERIOD_BYTES_MAX: u32 = 64 * 1024;
+/// The maximum number of periods.
+///
+/// Each period is an I/O message that takes two descriptors in the virtqueue, so all the periods
+/// of a buffer can be pending at the same time.
+const PERIODS_MAX: u32 = 16;
+const PERIODS_MIN: u32 = 2;
+const BUFFER_BYTES_MAX: u32 = 256 * 1024;
+
+/// `struct snd_mask` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct SndMask {
+    bits: [u32; 8],
+}
+
+/// `struct snd_interval` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct SndInterval {
+    min: u32,
+    max: u32,
+    /// The bit fields `openmin`, `openmax`, `integer`, and `empty`
+    flags: u32,
+}
+
+/// `struct snd_pcm_hw_params` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct SndPcmHwParams {
+    flags: u32,
+    masks: [SndMask; NR_MASKS],
+    mres: [SndMask; 5],
+    intervals: [SndInterval; NR_INTERVALS],
+    ires: [SndInterval; 9],
+    rmask: u32,
+    cmask: u32,
+    info: u32,
+    msbits: u32,
+    rate_num: u32,
+    rate_den: u32,
+    fifo_size: u64,
+    sync: [u8; 16],
+    reserved: [u8; 48],
+}
+
+/// A sample format that can be used by both ALSA and the virtio sound device.
+struct FormatDesc {
+    virtio: PcmFormat,
+    /// `SNDRV_PCM_FORMAT_*` in Linux
+    alsa: u32,
+    /// The number of bits that a sample occupies
+    physical_width: u32,
+    /// The number of significant bits of a sample
+    width: u32,
+}
+
+/// The sample formats, where the little-endian ones are used since the device is little-endian.
+///
+/// `VIRTIO_SND_PCM_FMT_IMA_ADPCM` is missing because its samples are not byte-aligned.
+const FORMATS: [FormatDesc; 24] = [
+    FormatDesc::new(PcmFormat::S8, 0, 8, 8),
+    FormatDesc::new(PcmFormat::U8, 1, 8, 8),
+    FormatDesc::new(PcmFormat::S16, 2, 16, 16),
+    FormatDesc::new(PcmFormat::U16, 4, 16, 16),
+    FormatDesc::new(PcmFormat::S24, 6, 32, 24),
+    FormatDesc::new(PcmFormat::U24, 8, 32, 24),
+    FormatDesc::new(PcmFormat::S32, 10, 32, 32),
+    FormatDesc::new(PcmFormat::U32, 12, 32, 32),
+    FormatDesc::new(PcmFormat::Float, 14, 32, 32),
+    FormatDesc::new(PcmFormat::Float64, 16, 64, 64),
+    FormatDesc::new(PcmFormat::Iec958Subframe, 18, 32, 32),
+    FormatDesc::new(PcmFormat::MuLaw, 20, 8, 8),
+    FormatDesc::new(PcmFormat::ALaw, 21, 8, 8),
+    FormatDesc::new(PcmFormat::S20, 25, 32, 20),
+    FormatDesc::new(PcmFormat::U20, 27, 32, 20),
+    FormatDesc::new(PcmFormat::S24_3, 32, 24, 24),
+    FormatDesc::new(PcmFormat::U24_3, 34, 24, 24),
+    FormatDesc::new(PcmFormat::S20_3, 36, 24, 20),
+    FormatDesc::new(PcmFormat::U20_3, 38, 24, 20),
+    FormatDesc::new(PcmFormat::S18_3, 40, 24, 18),
+    FormatDesc::new(PcmFormat::U18_3, 42, 24, 18),
+    FormatDesc::new(PcmFormat::DsdU8, 48, 8, 8),
+    FormatDesc::new(PcmFormat::DsdU16, 49, 16, 16),
+    FormatDesc::new(PcmFormat::DsdU32, 50, 32, 32),
+];
+
+impl FormatDesc {
+    const fn new(virtio: PcmFormat, alsa: u32, physical_width: u32, width: u32) -> Self {
+        Self {
+            virtio,
+            alsa,
+            physical_width,
+            width,
+        }
+    }
+
+    fn from_alsa(alsa: u32) -> Option<&'static Self> {
+        FORMATS.iter().find(|desc| desc.alsa == alsa)
+    }
+}
+
+/// The constraints that the hardware places on the parameters of a PCM stream.
+pub(super) struct HwConstraints {
+    /// The supported ALSA sample formats, where bit `n` stands for the format whose value is `n`
+    formats: u64,
+    /// The supported frame rates in ascending order
+    rates: Vec<(u32, PcmRate)>,
+    channels: Interval,
+}
+
+impl HwConstraints {
+    pub(super) fn new(info: &PcmInfo) -> Self {
+        let formats = info
+            .formats()
+            .filter_map(|format| FORMATS.iter().find(|desc| desc.virtio == format))
+            .fold(0, |mask, desc| mask | (1 << desc.alsa));
+
+        let mut rates: Vec<_> = info.rates().map(|rate| (rate.hz(), rate)).collect();
+        rates.sort_unstable_by_key(|(hz, _)| *hz);
+
+        let channels = Interval::new_integer(info.channels_min as u32, info.channels_max as u32);
+
+        Self {
+            formats,
+            rates,
+            channels,
+        }
+    }
+
+    /// Returns whether the stream can be used at all.
+    pub(super) fn is_usable(&self) -> bool {
+        self.formats != 0 && !self.rates.is_empty() && !self.channels.is_empty()
+    }
+
+    fn apply(&self, params: &mut Params) -> Result<u32> {
+        const ACCESS_MASK: u64 =
+            (1 << SNDRV_PCM_ACCESS_MMAP_INTERLEAVED) | (1 << SNDRV_PCM_ACCESS_RW_INTERLEAVED);
+
+        let mut changed = 0;
+
+        changed |= params.refine_mask(SNDRV_PCM_HW_PARAM_ACCESS, ACCESS_MASK)?;
+        changed |= params.refine_mask(SNDRV_PCM_HW_PARAM_FORMAT, self.formats)?;
+        changed |=
+            params.refine_mask(SNDRV_PCM_HW_PARAM_SUBFORMAT, 1 << SNDRV_PCM_SUBFORMAT_STD)?;
+        changed |= params.refine_interval(SNDRV_PCM_HW_PARAM_CHANNELS, &self.channels)?;
+        changed |= params.refine_interval(
+            SNDRV_PCM_HW_PARAM_PERIOD_BYTES,
+            &Interval::new(PERIOD_BYTES_MIN, PERIOD_BYTES_MAX),
+        )?;
+        changed |= params.refine_interval(
+            SNDRV_PCM_HW_PARAM_PERIODS,
+            &Interval::new_integer(PERIODS_MIN, PERIODS_MAX),
+        )?;
+        changed |= params.refine_interval(
+            SNDRV_PCM_HW_PARAM_BUFFER_BYTES,
+            &Interval::new_integer(PERIOD_BYTES_MIN, BUFFER_BYTES_MAX),
+        )?;
+        for index in [
+            SNDRV_PCM_HW_PARAM_SAMPLE_BITS,
+            SNDRV_PCM_HW_PARAM_FRAME_BITS,
+            SNDRV_PCM_HW_PARAM_BUFFER_SIZE,
+        ] {
+            changed |= params.refine_interval(index, &Interval::new_integer(0, u32::MAX))?;
+        }
+
+        Ok(changed)
+    }
+
+    fn apply_rates(&self, params: &mut Params) -> Result<u32> {
+        let rate = params.interval(SNDRV_PCM_HW_PARAM_RATE);
+        let mut range = Interval::new(u32::MAX, 0);
+        for (hz, _) in self.rates.iter().filter(|(hz, _)| rate.contains(*hz)) {
+            range.min = range.min.min(*hz);
+            range.max = range.max.max(*hz);
+        }
+        params.refine_interval(SNDRV_PCM_HW_PARAM_RATE, &range)
+    }
+
+    fn virtio_rate(&self, hz: u32) -> Option<PcmRate> {
+        self.rates
+            .iter()
+            .find(|(rate_hz, _)| *rate_hz == hz)
+            .map(|(_, rate)| *rate)
+    }
+}
+
+/// A relation between the interval parameters, which is `snd_pcm_hw_rule` in Linux.
+enum Relation {
+    /// `target = a * b`
+    Mul(usize, usize, usize),
+    /// `target = a / b`
+    Div(usize, usize, usize),
+    /// `target = a * b / k`
+    MulDivK(usize, usize, usize, u32),
+    /// `target = a * k / b`
+    MulKDiv(usize, usize, u32, usize),
+}
+
+const RELATIONS: [Relation; 18] = {
+    use Relation::*;
+
+    const USEC_PER_SEC: u32 = 1_000_000;
+
+    [
+        Div(
+            SNDRV_PCM_HW_PARAM_SAMPLE_BITS,
+            SNDRV_PCM_HW_PARAM_FRAME_BITS,
+            SNDRV_PCM_HW_PARAM_CHANNELS,
+        ),
+        Mul(
+            SNDRV_PCM_HW_PARAM_FRAME_BITS,
+            SNDRV_PCM_HW_PARAM_SAMPLE_BITS,
+            SNDRV_PCM_HW_PARAM_CHANNELS,
+        ),
+        MulKDiv(
+            SNDRV_PCM_HW_PARAM_FRAME_BITS,
+            SNDRV_PCM_HW_PARAM_PERIOD_BYTES,
+            8,
+            SNDRV_PCM_HW_PARAM_PERIOD_SIZE,
+        ),
+        MulKDiv(
+            SNDRV_PCM_HW_PARAM_FRAME_BITS,
+            SNDRV_PCM_HW_PARAM_BUFFER_BYTES,
+            8,
+            SNDRV_PCM_HW_PARAM_BUFFER_SIZE,
+        ),
+        Div(
+            SNDRV_PCM_HW_PARAM_CHANNELS,
+            SNDRV_PCM_HW_PARAM_FRAME_BITS,
+            SNDRV_PCM_HW_PARAM_SAMPLE_BITS,
+        ),
+        MulKDiv(
+            SNDRV_PCM_HW_PARAM_RATE,
+            SNDRV_PCM_HW_PARAM_PERIOD_SIZE,
+            USEC_PER_SEC,
+            SNDRV_PCM_HW_PARAM_PERIOD_TIME,
+        ),
+        MulKDiv(
+            SNDRV_PCM_HW_PARAM_RATE,
+            SNDRV_PCM_HW_PARAM_BUFFER_SIZE,
+            USEC_PER_SEC,
+            SNDRV_PCM_HW_PARAM_BUFFER_TIME,
+        ),
+        Div(
+            SNDRV_PCM_HW_PARAM_PERIODS,
+            SNDRV_PCM_HW_PARAM_BUFFER_SIZE,
+            SNDRV_PCM_HW_PARAM_PERIOD_SIZE,
+        ),
+        Div(
+            SNDRV_PCM_HW_PARAM_PERIOD_SIZE,
+            SNDRV_PCM_HW_PARAM_BUFFER_SIZE,
+            SNDRV_PCM_HW_PARAM_PERIODS,
+        ),
+        MulKDiv(
+            SNDRV_PCM_HW_PARAM_PERIOD_SIZE,
+            SNDRV_PCM_HW_PARAM_PERIOD_BYTES,
+            8,
+            SNDRV_PCM_HW_PARAM_FRAME_BITS,
+        ),
+        MulDivK(
+            SNDRV_PCM_HW_PARAM_PERIOD_SIZE,
+            SNDRV_PCM_HW_PARAM_PERIOD_TIME,
+            SNDRV_PCM_HW_PARAM_RATE,
+            USEC_PER_SEC,
+        ),
+        Mul(
+            SNDRV_PCM_HW_PARAM_BUFFER_SIZE,
+            SNDRV_PCM_HW_PARAM_PERIOD_SIZE,
+            SNDRV_PCM_HW_PARAM_PERIODS,
+        ),
+        MulKDiv(
+            SNDRV_PCM_HW_PARAM_BUFFER_SIZE,
+            SNDRV_PCM_HW_PARAM_BUFFER_BYTES,
+            8,
+            SNDRV_PCM_HW_PARAM_FRAME_BITS,
+        ),
+        MulDivK(
+            SNDRV_PCM_HW_PARAM_BUFFER_SIZE,
+            SNDRV_PCM_HW_PARAM_BUFFER_TIME,
+            SNDRV_PCM_HW_PARAM_RATE,
+            USEC_PER_SEC,
+        ),
+        MulDivK(
+            SNDRV_PCM_HW_PARAM_PERIOD_BYTES,
+            SNDRV_PCM_HW_PARAM_PERIOD_SIZE,
+            SNDRV_PCM_HW_PARAM_FRAME_BITS,
+            8,
+        ),
+        MulKDiv(
+            SNDRV_PCM_HW_PARAM_PERIOD_TIME,
+            SNDRV_PCM_HW_PARAM_PERIOD_SIZE,
+            USEC_PER_SEC,
+            SNDRV_PCM_HW_PARAM_RATE,
+        ),
+        MulDivK(
+            SNDRV_PCM_HW_PARAM_BUFFER_BYTES,
+            SNDRV_PCM_HW_PARAM_BUFFER_SIZE,
+            SNDRV_PCM_HW_PARAM_FRAME_BITS,
+            8,
+        ),
+        MulKDiv(
+            SNDRV_PCM_HW_PARAM_BUFFER_TIME,
+            SNDRV_PCM_HW_PARAM_BUFFER_SIZE,
+            USEC_PER_SEC,
+            SNDRV_PCM_HW_PARAM_RATE,
+        ),
+    ]
+};
+
+/// The configuration that is chosen by [`SndPcmHwParams::choose`].
+#[derive(Debug, Clone, Copy)]
+pub(super) struct HwConfig {
+    pub(super) access: u32,
+    pub(super) virtio_format: PcmFormat,
+    pub(super) virtio_rate: PcmRate,
+    pub(super) channels: u32,
+    pub(super) frame_bytes: usize,
+    pub(super) period_size: usize,
+    pub(super) buffer_size: usize,
+}
+
+impl HwConfig {
+    pub(super) fn period_bytes(&self) -> usize {
+        self.period_size * self.frame_bytes
+    }
+
+    pub(super) fn buffer_bytes(&self) -> usize {
+        self.buffer_size * self.frame_bytes
+    }
+}
+
+impl SndPcmHwParams {
+    /// Refines the configuration space, which is what `SNDRV_PCM_IOCTL_HW_REFINE` does.
+    pub(super) fn refine(&mut self, hw: &HwConstraints) -> Result<()> {
+        let mut params = Params::from_raw(self);
+        let changed = params.refine(hw)?;
+        params.to_raw(self);
+
+        self.cmask |= changed;
+        self.rmask = 0;
+        self.fixup_unreferenced(&params);
+
+        Ok(())
+    }
+
+    /// Chooses a configuration from the configuration space, which is what
+    /// `SNDRV_PCM_IOCTL_HW_PARAMS` does.
+    pub(super) fn choose(&mut self, hw: &HwConstraints) -> Result<HwConfig> {
+        // Like Linux, the minimum values are chosen except for the buffer size.
+        const CHOICES: [(usize, bool); 7] = [
+            (SNDRV_PCM_HW_PARAM_ACCESS, false),
+            (SNDRV_PCM_HW_PARAM_FORMAT, false),
+            (SNDRV_PCM_HW_PARAM_SUBFORMAT, false),
+            (SNDRV_PCM_HW_PARAM_CHANNELS, false),
+            (SNDRV_PCM_HW_PARAM_RATE, false),
+            (SNDRV_PCM_HW_PARAM_PERIOD_TIME, false),
+            (SNDRV_PCM_HW_PARAM_BUFFER_SIZE, true),
+        ];
+
+        let mut params = Params::from_raw(self);
+        let mut changed = params.refine(hw)?;
+        for (index, is_last) in CHOICES {
+            if index < NR_MASKS {
+                let mask = params.masks[index];
+                params.masks[index] = mask & mask.wrapping_neg();
+            } else if is_last {
+                params.interval_mut(index).choose_last();
+            } else {
+                params.interval_mut(index).choose_first();
+            }
+            changed |= 1 << index;
+            changed |= params.refine(hw)?;
+        }
+        params.to_raw(self);
+
+        self.cmask |= changed;
+        self.rmask = 0;
+        self.fixup_unreferenced(&params);
+
+        let access = params.masks[SNDRV_PCM_HW_PARAM_ACCESS].trailing_zeros();
+        let format =
+            FormatDesc::from_alsa(params.masks[SNDRV_PCM_HW_PARAM_FORMAT].trailing_zeros())
+                .unwrap();
+        let rate = params.interval(SNDRV_PCM_HW_PARAM_RATE).value();
+        let channels = params.interval(SNDRV_PCM_HW_PARAM_CHANNELS).value();
+        let period_size = params.interval(SNDRV_PCM_HW_PARAM_PERIOD_SIZE).value() as usize;
+        let buffer_size = params.interval(SNDRV_PCM_HW_PARAM_BUFFER_SIZE).value() as usize;
+
+        if period_size == 0 || !buffer_size.is_multiple_of(period_size) {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "the buffer size is not a multiple of the period size"
+            );
+        }
+        let Some(virtio_rate) = hw.virtio_rate(rate) else {
+            return_errno_with_message!(Errno::EINVAL, "the rate is not supported");
+        };
+
+        Ok(HwConfig {
+            access,
+            virtio_format: format.virtio,
+            virtio_rate,
+            channels,
+            frame_bytes: (format.physical_width * channels / 8) as usize,
+            period_size,
+            buffer_size,
+        })
+    }
+
+    /// Fills the fields that are not parameters, as `fixup_unreferenced_params` in Linux does.
+    fn fixup_unreferenced(&mut self, params: &Params) {
+        let formats = params.masks[SNDRV_PCM_HW_PARAM_FORMAT];
+        if formats.is_power_of_two()
+            && let Some(format) = FormatDesc::from_alsa(formats.trailing_zeros())
+        {
+            self.msbits = format.width;
+        } else if self.msbits == 0 {
+            let sample_bits = params.interval(SNDRV_PCM_HW_PARAM_SAMPLE_BITS);
+            if sample_bits.is_single() {
+                self.msbits = sample_bits.value();
+            }
+        }
+
+        let rate = params.interval(SNDRV_PCM_HW_PARAM_RATE);
+        if self.rate_den == 0 && rate.is_single() {
+            self.rate_num = rate.value();
+            self.rate_den = 1;
+        }
+
+        self.info = PCM_INFO;
+        self.fifo_size = 0;
+    }
+}
+
+/// The parameters in the form that is convenient for the refinement.
+struct Params {
+    /// The masks, where only the first 64 bits are kept since no values are larger.
+    masks: [u64; NR_MASKS],
+    intervals: [Interval; NR_INTERVALS],
+}
+
+impl Params {
+    fn from_raw(raw: &SndPcmHwParams) -> Self {
+        Self {
+            masks: raw
+                .masks
+                .map(|mask| mask.bits[0] as u64 | ((mask.bits[1] as u64) << 32)),
+            intervals: raw.intervals.map(Interval::from_raw),
+        }
+    }
+
+    fn to_raw(&self, raw: &mut SndPcmHwParams) {
+        for (raw_mask, mask) in raw.masks.iter_mut().zip(self.masks) {
+            raw_mask.bits = [0; 8];
+            raw_mask.bits[0] = mask as u32;
+            raw_mask.bits[1] = (mask >> 32) as u32;
+        }
+        for (raw_interval, interval) in raw.intervals.iter_mut().zip(self.intervals.iter()) {
+            *raw_interval = interval.to_raw();
+        }
+    }
+
+    fn interval(&self, index: usize) -> Interval {
+        self.intervals[index - FIRST_INTERVAL]
+    }
+
+    fn interval_mut(&mut self, index: usize) -> &mut Interval {
+        &mut self.intervals[index - FIRST_INTERVAL]
+    }
+
+    /// Refines the parameters until they are consistent.
+    ///
+    /// On success, a bitmask of the changed parameters is returned.
+    fn refine(&mut self, hw: &HwConstraints) -> Result<u32> {
+        // Linux stops propagating the changes after a number of rounds as well.
+        const MAX_ROUNDS: usize = 64;
+
+        let mut changed = hw.apply(self)?;
+        for _ in 0..MAX_ROUNDS {
+            let mut round_changed = self.apply_formats()?;
+            round_changed |= hw.apply_rates(self)?;
+            for relation in RELATIONS.iter() {
+                round_changed |= self.apply_relation(relation)?;
+            }
+
+            if round_changed == 0 {
+                break;
+            }
+            changed |= round_changed;
+        }
+
+        Ok(changed)
+    }
+
+    /// Applies the relations between the sample formats and the sample bits.
+    fn apply_formats(&mut self) -> Result<u32> {
+        let sample_bits = self.interval(SNDRV_PCM_HW_PARAM_SAMPLE_BITS);
+        let formats = FORMATS
+            .iter()
+            .filter(|desc| sample_bits.contains(desc.physical_width))
+            .fold(0, |mask, desc| mask | (1 << desc.alsa));
+        let mut changed = self.refine_mask(SNDRV_PCM_HW_PARAM_FORMAT, formats)?;
+
+        let mut range = Interval::new_integer(u32::MAX, 0);
+        for desc in FORMATS
+            .iter()
+            .filter(|desc| self.masks[SNDRV_PCM_HW_PARAM_FORMAT] & (1 << desc.alsa) != 0)
+        {
+            range.min = range.min.min(desc.physical_width);
+            range.max = range.max.max(desc.physical_width);
+        }
+        changed |= self.refine_interval(SNDRV_PCM_HW_PARAM_SAMPLE_BITS, &range)?;
+
+        Ok(changed)
+    }
+
+    fn apply_relation(&mut self, relation: &Relation) -> Result<u32> {
+        let (target, value) = match *relation {
+            Relation::Mul(target, a, b) => (target, self.interval(a).mul(&self.interval(b))),
+            Relation::Div(target, a, b) => (target, self.interval(a).div(&self.interval(b))),
+            Relation::MulDivK(target, a, b, k) => {
+                (target, self.interval(a).mul_div_k(&self.interval(b), k))
+            }
+            Relation::MulKDiv(target, a, k, b) => {
+                (target, self.interval(a).mul_k_div(k, &self.interval(b)))
+            }
+        };
+        self.refine_interval(target, &value)
+    }
+
+    fn refine_mask(&mut self, index: usize, value: u64) -> Result<u32> {
+        let mask = &mut self.masks[index];
+        let refined = *mask & value;
+        if refined == 0 {
+            return_errno_with_message!(Errno::EINVAL, "the hardware parameters are unsatisfiable");
+        }
+
+        let changed = refined != *mask;
+        *mask = refined;
+        Ok((changed as u32) << index)
+    }
+
+    fn refine_interval(&mut self, index: usize, value: &Interval) -> Result<u32> {
+        let changed = self.interval_mut(index).refine(value)?;
+        Ok((changed as u32) << index)
+    }
+}
+
+/// An interval of the values of a parameter, which is `struct snd_interval` in Linux.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Interval {
+    min: u32,
+    max: u32,
+    open_min: bool,
+    open_max: bool,
+    integer: bool,
+    empty: bool,
+}
+
+impl Interval {
+    const OPEN_MIN: u32 = 1 << 0;
+    const OPEN_MAX: u32 = 1 << 1;
+    const INTEGER: u32 = 1 << 2;
+    const EMPTY: u32 = 1 << 3;
+
+    const fn new(min: u32, max: u32) -> Self {
+        Self {
+            min,
+            max,
+            open_min: false,
+            open_max: false,
+            integer: false,
+            empty: false,
+        }
+    }
+
+    const fn new_integer(min: u32, max: u32) -> Self {
+        Self {
+            integer: true,
+            ..Self::new(min, max)
+        }
+    }
+
+    const fn empty() -> Self {
+        Self {
+            empty: true,
+            ..Self::new(0, 0)
+        }
+    }
+
+    fn from_raw(raw: SndInterval) -> Self {
+        Self {
+            min: raw.min,
+            max: raw.max,
+            open_min: raw.flags & Self::OPEN_MIN != 0,
+            open_max: raw.flags & Self::OPEN_MAX != 0,
+            integer: raw.flags & Self::INTEGER != 0,
+            empty: raw.flags & Self::EMPTY != 0,
+        }
+    }
+
+    fn to_raw(self) -> SndInterval {
+        let mut flags = 0;
+        for (is_set, flag) in [
+            (self.open_min, Self::OPEN_MIN),
+            (self.open_max, Self::OPEN_MAX),
+            (self.integer, Self::INTEGER),
+            (self.empty, Self::EMPTY),
+        ] {
+            if is_set {
+                flags |= flag;
+            }
+        }
+
+        SndInterval {
+            min: self.min,
+            max: self.max,
+            flags,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.empty
+            || self.min > self.max
+            || (self.min == self.max && (self.open_min || self.open_max))
+    }
+
+    fn is_single(&self) -> bool {
+        !self.empty
+            && (self.min == self.max
+                || (self.min.checked_add(1) == Some(self.max) && (self.open_min || self.open_max)))
+    }
+
+    /// Returns the value of a single interval.
+    fn value(&self) -> u32 {
+        if self.open_min && !self.open_max {
+            self.max
+        } else {
+            self.min
+        }
+    }
+
+    fn contains(&self, value: u32) -> bool {
+        !(self.min > value
+            || (self.min == value && self.open_min)
+            || self.max < value
+            || (self.max == value && self.open_max))
+    }
+
+    /// Intersects the interval with another one, as `snd_interval_refine` in Linux does.
+    ///
+    /// On success, whether the interval is changed is returned.
+    fn refine(&mut self, value: &Interval) -> Result<bool> {
+        if self.empty || value.empty {
+            return_errno_with_message!(Errno::EINVAL, "the hardware parameters are unsatisfiable");
+        }
+
+        let old = *self;
+
+        if self.min < value.min {
+            self.min = value.min;
+            self.open_min = value.open_min;
+        } else if self.min == value.min && value.open_min {
+            self.open_min = true;
+        }
+        if self.max > value.max {
+            self.max = value.max;
+            self.open_max = value.open_max;
+        } else if self.max == value.max && value.open_max {
+            self.open_max = true;
+        }
+        self.integer |= value.integer;
+
+        if self.integer {
+            if self.open_min {
+                self.min = self.min.saturating_add(1);
+                self.open_min = false;
+            }
+            if self.open_max {
+                self.max = self.max.saturating_sub(1);
+                self.open_max = false;
+            }
+        } else if !self.open_min && !self.open_max && self.min == self.max {
+            self.integer = true;
+        }
+
+        if self.is_empty() {
+            *self = Self::empty();
+            return_errno_with_message!(Errno::EINVAL, "the hardware parameters are unsatisfiable");
+        }
+
+        Ok(*self != old)
+    }
+
+    /// Keeps the minimum value only, as `snd_interval_refine_first` in Linux does.
+    fn choose_first(&mut self) {
+        if self.is_single() {
+            return;
+        }
+
+        let last_max = self.max;
+        self.max = self.min;
+        if self.open_min {
+            self.max += 1;
+        }
+        self.open_max = self.open_max && self.max >= last_max;
+    }
+
+    /// Keeps the maximum value only, as `snd_interval_refine_last` in Linux does.
+    fn choose_last(&mut self) {
+        if self.is_single() {
+            return;
+        }
+
+        let last_min = self.min;
+        self.min = self.max;
+        if self.open_max {
+            self.min -= 1;
+        }
+        self.open_min = self.open_min && self.min <= last_min;
+    }
+
+    fn mul(&self, other: &Interval) -> Interval {
+        if self.empty || other.empty {
+            return Self::empty();
+        }
+
+        Interval {
+            min: self.min.saturating_mul(other.min),
+            max: self.max.saturating_mul(other.max),
+            open_min: self.open_min || other.open_min,
+            open_max: self.open_max || other.open_max,
+            integer: self.integer && other.integer,
+            empty: false,
+        }
+    }
+
+    fn div(&self, other: &Interval) -> Interval {
+        if self.empty || other.empty {
+            return Self::empty();
+        }
+
+        let (min, rem) = div_rem(self.min, other.max);
+        let mut result = Interval::new(min, u32::MAX);
+        result.open_min = rem != 0 || self.open_min || other.open_max;
+        if other.min > 0 {
+            let (max, rem) = div_rem(self.max, other.min);
+            result.max = max;
+            if rem != 0 {
+                result.max = result.max.saturating_add(1);
+                result.open_max = true;
+            } else {
+                result.open_max = self.open_max || other.open_min;
+            }
+        }
+        result
+    }
+
+    fn mul_div_k(&self, other: &Interval, k: u32) -> Interval {
+        if self.empty || other.empty {
+            return Self::empty();
+        }
+
+        let (min, rem) = mul_div(self.min, other.min, k);
+        let mut result = Interval::new(min, 0);
+        result.open_min = rem != 0 || self.open_min || other.open_min;
+        let (max, rem) = mul_div(self.max, other.max, k);
+        result.max = max;
+        if rem != 0 {
+            result.max = result.max.saturating_add(1);
+            result.open_max = true;
+        } else {
+            result.open_max = self.open_max || other.open_max;
+        }
+        result
+    }
+
+    fn mul_k_div(&self, k: u32, other: &Interval) -> Interval {
+        if self.empty || other.empty {
+            return Self::empty();
+        }
+
+        let (min, rem) = mul_div(self.min, k, other.max);
+        let mut result = Interval::new(min, u32::MAX);
+        result.open_min = rem != 0 || self.open_min || other.open_max;
+        if other.min > 0 {
+            let (max, rem) = mul_div(self.max, k, other.min);
+            result.max = max;
+            if rem != 0 {
+                result.max = result.max.saturating_add(1);
+                result.open_max = true;
+            } else {
+                result.open_max = self.open_max || other.open_min;
+            }
+        }
+        result
+    }
+}
+
+/// Returns the quotient and the remainder, where dividing by zero yields `u32::MAX`.
+fn div_rem(a: u32, b: u32) -> (u32, u32) {
+    if b == 0 {
+        return (u32::MAX, 0);
+    }
+    (a / b, a % b)
+}
+
+/// Returns the quotient and the remainder of `a * b / c`, where the quotient saturates to
+/// `u32::MAX`.
+fn mul_div(a: u32, b: u32, c: u32) -> (u32, u32) {
+    if c == 0 {
+        return (u32::MAX, 0);
+    }
+
+    let n = a as u64 * b as u64;
+    let (quotient, rem) = (n / c as u64, (n % c as u64) as u32);
+    if quotient >= u32::MAX as u64 {
+        return (u32::MAX, 0);
+    }
+    (quotient as u32, rem)
+}
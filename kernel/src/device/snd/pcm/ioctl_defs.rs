@@ -0,0 +1,64 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::{
+    SndPcmInfo,
+    file::{SndPcmChannelInfo, SndPcmStatus, SndPcmSwParams, SndPcmSyncPtr, SndXferi},
+    hw_params::SndPcmHwParams,
+};
+use crate::util::ioctl::{InData, InOutData, NoData, OutData, PassByVal, ioc};
+
+// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/sound/asound.h>
+
+pub(super) type GetPversion =
+    ioc!(SNDRV_PCM_IOCTL_PVERSION,      b'A', 0x00, OutData<i32>);
+pub(super) type GetInfo =
+    ioc!(SNDRV_PCM_IOCTL_INFO,          b'A', 0x01, OutData<SndPcmInfo>);
+pub(super) type SetTstamp =
+    ioc!(SNDRV_PCM_IOCTL_TSTAMP,        b'A', 0x02, InData<i32>);
+pub(super) type SetTtstamp =
+    ioc!(SNDRV_PCM_IOCTL_TTSTAMP,       b'A', 0x03, InData<i32>);
+pub(super) type SetUserPversion =
+    ioc!(SNDRV_PCM_IOCTL_USER_PVERSION, b'A', 0x04, InData<i32>);
+
+pub(super) type HwRefine =
+    ioc!(SNDRV_PCM_IOCTL_HW_REFINE,     b'A', 0x10, InOutData<SndPcmHwParams>);
+pub(super) type HwParams =
+    ioc!(SNDRV_PCM_IOCTL_HW_PARAMS,     b'A', 0x11, InOutData<SndPcmHwParams>);
+pub(super) type HwFree =
+    ioc!(SNDRV_PCM_IOCTL_HW_FREE,       b'A', 0x12, NoData);
+pub(super) type SwParams =
+    ioc!(SNDRV_PCM_IOCTL_SW_PARAMS,     b'A', 0x13, InOutData<SndPcmSwParams>);
+
+pub(super) type GetStatus =
+    ioc!(SNDRV_PCM_IOCTL_STATUS,        b'A', 0x20, OutData<SndPcmStatus>);
+pub(super) type GetDelay =
+    ioc!(SNDRV_PCM_IOCTL_DELAY,         b'A', 0x21, OutData<i64>);
+pub(super) type HwSync =
+    ioc!(SNDRV_PCM_IOCTL_HWSYNC,        b'A', 0x22, NoData);
+pub(super) type SyncPtr =
+    ioc!(SNDRV_PCM_IOCTL_SYNC_PTR,      b'A', 0x23, InOutData<SndPcmSyncPtr>);
+pub(super) type GetStatusExt =
+    ioc!(SNDRV_PCM_IOCTL_STATUS_EXT,    b'A', 0x24, InOutData<SndPcmStatus>);
+// Linux declares `SNDRV_PCM_IOCTL_CHANNEL_INFO` with `_IOR`, but the channel is an input.
+pub(super) type GetChannelInfo =
+    ioc!(SNDRV_PCM_IOCTL_CHANNEL_INFO,  b'A', 0x32, OutData<SndPcmChannelInfo>);
+
+pub(super) type Prepare =
+    ioc!(SNDRV_PCM_IOCTL_PREPARE,       b'A', 0x40, NoData);
+pub(super) type Reset =
+    ioc!(SNDRV_PCM_IOCTL_RESET,         b'A', 0x41, NoData);
+pub(super) type Start =
+    ioc!(SNDRV_PCM_IOCTL_START,         b'A', 0x42, NoData);
+pub(super) type DropFrames =
+    ioc!(SNDRV_PCM_IOCTL_DROP,          b'A', 0x43, NoData);
+pub(super) type Drain =
+    ioc!(SNDRV_PCM_IOCTL_DRAIN,         b'A', 0x44, NoData);
+pub(super) type Pause =
+    ioc!(SNDRV_PCM_IOCTL_PAUSE,         b'A', 0x45, InData<i32, PassByVal>);
+
+// Linux declares the transfer ioctls with `_IOW` or `_IOR`, but `struct snd_xferi` is always an
+// input and its `result` field is always an output.
+pub(super) type WriteiFrames =
+    ioc!(SNDRV_PCM_IOCTL_WRITEI_FRAMES, b'A', 0x50, InData<SndXferi>);
+pub(super) type ReadiFrames =
+    ioc!(SNDRV_PCM_IOCTL_READI_FRAMES,  b'A', 0x51, OutData<SndXferi>);
@@ -0,0 +1,220 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! PCM devices (`/dev/snd/pcmC<card>D<device>{p,c}`).
+//!
+//! Each PCM stream of a virtio sound device is exposed as a PCM device with a single substream,
+//! which can be opened by one file at a time.
+
+mod file;
+mod hw_params;
+mod ioctl_defs;
+mod runtime;
+
+use alloc::format;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use aster_virtio::device::sound::{
+    PcmBuffer, PcmDirection, PcmInfo, SoundError, device::SoundDevice,
+};
+use device_id::{DeviceId, MinorId};
+use ostd::sync::SpinLock;
+
+use self::{file::PcmFile, hw_params::HwConstraints, runtime::Runtime};
+use super::SND_MAJOR;
+use crate::{
+    device::{Device, DeviceType},
+    events::IoEvents,
+    fs::file::FileIo,
+    prelude::*,
+    process::signal::Pollee,
+    thread::work_queue::{WorkPriority, submit_work_item, work_item::WorkItem},
+};
+
+/// The completion of an I/O message.
+type XferCompletion = (Arc<PcmBuffer>, core::result::Result<usize, SoundError>);
+
+pub(super) struct PcmDevice {
+    id: DeviceId,
+    /// The index of the sound card
+    card: u32,
+    /// The index of the PCM device in the sound card
+    device: u32,
+    info: PcmInfo,
+    hw: HwConstraints,
+    runtime: Mutex<Runtime>,
+    /// The I/O messages that are completed but not handled.
+    ///
+    /// The completions are reported in the interrupt context, but handling them requires
+    /// accessing the ring buffer and sending requests to the device, which may sleep. So they
+    /// are handled in a work item.
+    completions: SpinLock<VecDeque<XferCompletion>>,
+    completion_work: Arc<WorkItem>,
+    pollee: Pollee,
+    is_opened: AtomicBool,
+    weak_self: Weak<Self>,
+}
+
+impl PcmDevice {
+    /// Creates a PCM device for a PCM stream, or returns `None` if the stream cannot be used.
+    pub(super) fn new(
+        sound: Arc<SoundDevice>,
+        info: PcmInfo,
+        card: u32,
+        device: u32,
+    ) -> Option<Arc<Self>> {
+        let hw = HwConstraints::new(&info);
+        if !hw.is_usable() {
+            return None;
+        }
+
+        // Like Linux, the minor numbers of the PCM devices are static.
+        // Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/sound/minors.h>.
+        let minor_base = match info.direction {
+            PcmDirection::Output => 16,
+            PcmDirection::Input => 24,
+        };
+        let id = DeviceId::new(
+            SND_MAJOR.get().unwrap().get(),
+            MinorId::new(card * 32 + minor_base + device),
+        );
+
+        let pcm = Arc::new_cyclic(|weak_self: &Weak<Self>| {
+            let weak_pcm = weak_self.clone();
+            let completion_work = WorkItem::new(Box::new(move || {
+                if let Some(pcm) = weak_pcm.upgrade() {
+                    pcm.handle_completions();
+                }
+            }));
+
+            Self {
+                id,
+                card,
+                device,
+                info,
+                hw,
+                runtime: Mutex::new(Runtime::new(sound.clone(), info.stream_id, info.direction)),
+                completions: SpinLock::new(VecDeque::new()),
+                completion_work,
+                pollee: Pollee::new(),
+                is_opened: AtomicBool::new(false),
+                weak_self: weak_self.clone(),
+            }
+        });
+
+        let weak_pcm = Arc::downgrade(&pcm);
+        sound.set_xfer_callback(
+            info.stream_id,
+            Box::new(move |buffer, result| {
+                let Some(pcm) = weak_pcm.upgrade() else {
+                    return;
+                };
+                pcm.completions
+                    .disable_irq()
+                    .lock()
+                    .push_back((buffer.clone(), result));
+                submit_work_item(pcm.completion_work.clone(), WorkPriority::High);
+            }),
+        );
+
+        Some(pcm)
+    }
+
+    pub(super) fn device(&self) -> u32 {
+        self.device
+    }
+
+    pub(super) fn direction(&self) -> PcmDirection {
+        self.info.direction
+    }
+
+    /// Fills the information of the PCM device, which is `struct snd_pcm_info` in Linux.
+    pub(super) fn fill_info(&self, info: &mut SndPcmInfo) {
+        const SNDRV_PCM_CLASS_GENERIC: i32 = 0;
+
+        info.device = self.device;
+        info.subdevice = 0;
+        info.stream = self.info.direction as i32;
+        info.card = self.card as i32;
+        copy_str(&mut info.id, &format!("virtio-snd {}", self.device));
+        copy_str(&mut info.name, "VirtIO PCM");
+        copy_str(&mut info.subname, "subdevice #0");
+        info.dev_class = SNDRV_PCM_CLASS_GENERIC;
+        info.dev_subclass = 0;
+        info.subdevices_count = 1;
+        info.subdevices_avail = (!self.is_opened.load(Ordering::Relaxed)) as u32;
+    }
+
+    fn handle_completions(&self) {
+        loop {
+            let Some((buffer, result)) = self.completions.disable_irq().lock().pop_front() else {
+                break;
+            };
+            self.runtime.lock().complete(buffer, result);
+        }
+
+        self.pollee.notify(IoEvents::IN | IoEvents::OUT);
+    }
+
+    fn close(&self) {
+        self.runtime.lock().close();
+        self.is_opened.store(false, Ordering::Relaxed);
+        self.pollee.notify(IoEvents::IN | IoEvents::OUT);
+    }
+}
+
+impl Device for PcmDevice {
+    fn type_(&self) -> DeviceType {
+        DeviceType::Char
+    }
+
+    fn id(&self) -> DeviceId {
+        self.id
+    }
+
+    fn devtmpfs_path(&self) -> Option<String> {
+        let suffix = match self.info.direction {
+            PcmDirection::Output => 'p',
+            PcmDirection::Input => 'c',
+        };
+        Some(format!("snd/pcmC{}D{}{}", self.card, self.device, suffix))
+    }
+
+    fn class(&self) -> &'static str {
+        "sound"
+    }
+
+    fn open(&self) -> Result<Box<dyn FileIo>> {
+        if self.is_opened.swap(true, Ordering::Relaxed) {
+            return_errno_with_message!(Errno::EBUSY, "the PCM device is already opened");
+        }
+
+        let pcm = self.weak_self.upgrade().unwrap();
+        Ok(Box::new(PcmFile::new(pcm)))
+    }
+}
+
+/// `struct snd_pcm_info` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct SndPcmInfo {
+    pub(super) device: u32,
+    pub(super) subdevice: u32,
+    pub(super) stream: i32,
+    card: i32,
+    id: [u8; 64],
+    name: [u8; 80],
+    subname: [u8; 32],
+    dev_class: i32,
+    dev_subclass: i32,
+    subdevices_count: u32,
+    subdevices_avail: u32,
+    sync: [u8; 16],
+    reserved: [u8; 64],
+}
+
+/// Copies a string to a C string buffer, which is truncated if the buffer is too small.
+pub(super) fn copy_str(buf: &mut [u8], value: &str) {
+    let len = value.len().min(buf.len() - 1);
+    buf[..len].copy_from_slice(&value.as_bytes()[..len]);
+    buf[len..].fill(0);
+}
@@ -0,0 +1,770 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The runtime state of PCM streams.
+//!
+//! The frames of a stream live in a ring buffer, which is a VMO that can be mapped by the user
+//! space. The application pointer (`appl_ptr`) is where the user space writes or reads the next
+//! frame, and the hardware pointer (`hw_ptr`) is where the device plays or records the next frame.
+//! Like Linux, both pointers wrap around at the boundary, which is a multiple of the buffer size.
+//!
+//! For playback streams, the full periods between the two pointers are copied to the I/O messages
+//! and submitted to the device. For capture streams, all the I/O messages are submitted when the
+//! stream starts, and the captured frames are copied to the ring buffer when the messages are
+//! completed. In both cases, the hardware pointer moves forward when a message is completed.
+
+use core::time::Duration;
+
+use align_ext::AlignExt;
+use aster_virtio::device::sound::{
+    PcmBuffer, PcmDirection, PcmParams, SoundError, device::SoundDevice,
+};
+use ostd::mm::VmIo;
+
+use super::hw_params::HwConfig;
+use crate::{
+    events::IoEvents,
+    prelude::*,
+    time::clocks::{MonotonicClock, MonotonicRawClock, RealTimeClock},
+    vm::vmo::{Vmo, VmoOptions},
+};
+
+/// `snd_pcm_state_t` in Linux.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum PcmState {
+    Open = 0,
+    Setup = 1,
+    Prepared = 2,
+    Running = 3,
+    Xrun = 4,
+    Draining = 5,
+    Paused = 6,
+}
+
+/// `SNDRV_PCM_TSTAMP_TYPE_*` in Linux.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+pub(super) enum TstampType {
+    GetTimeOfDay = 0,
+    Monotonic = 1,
+    MonotonicRaw = 2,
+}
+
+impl TstampType {
+    fn now(self) -> Duration {
+        match self {
+            Self::GetTimeOfDay => RealTimeClock::get().read_time(),
+            Self::Monotonic => MonotonicClock::get().read_time(),
+            Self::MonotonicRaw => MonotonicRawClock::get().read_time(),
+        }
+    }
+}
+
+/// The software parameters that are used by the kernel.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct SwConfig {
+    pub(super) avail_min: usize,
+    pub(super) start_threshold: usize,
+    pub(super) stop_threshold: usize,
+}
+
+/// A snapshot of the status of a PCM stream.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct PcmStatus {
+    pub(super) state: PcmState,
+    pub(super) hw_ptr: usize,
+    pub(super) appl_ptr: usize,
+    /// The number of frames that can be written (for playback) or read (for capture)
+    pub(super) avail: usize,
+    /// The number of frames that are yet to be played (for playback) or read (for capture)
+    pub(super) delay: usize,
+    pub(super) avail_min: usize,
+    pub(super) trigger_tstamp: Duration,
+    pub(super) tstamp: Duration,
+}
+
+pub(super) struct Runtime {
+    sound: Arc<SoundDevice>,
+    stream_id: u32,
+    direction: PcmDirection,
+    state: PcmState,
+    setup: Option<Setup>,
+    /// Whether the stream is prepared on the host
+    is_host_prepared: bool,
+    tstamp_type: TstampType,
+    trigger_tstamp: Duration,
+}
+
+/// The resources of a PCM stream whose hardware parameters are set.
+struct Setup {
+    config: HwConfig,
+    sw: SwConfig,
+    boundary: usize,
+    vmo: Arc<Vmo>,
+    hw_ptr: usize,
+    appl_ptr: usize,
+    free_buffers: Vec<Arc<PcmBuffer>>,
+    /// The I/O messages that are owned by the device, in the order of submission.
+    pending: VecDeque<PendingXfer>,
+    /// The number of frames in the pending messages that are not stale.
+    pending_frames: usize,
+}
+
+struct PendingXfer {
+    buffer: Arc<PcmBuffer>,
+    frames: usize,
+    /// Whether the message is submitted before the stream is stopped
+    is_stale: bool,
+}
+
+impl Runtime {
+    pub(super) fn new(sound: Arc<SoundDevice>, stream_id: u32, direction: PcmDirection) -> Self {
+        Self {
+            sound,
+            stream_id,
+            direction,
+            state: PcmState::Open,
+            setup: None,
+            is_host_prepared: false,
+            tstamp_type: TstampType::GetTimeOfDay,
+            trigger_tstamp: Duration::ZERO,
+        }
+    }
+
+    pub(super) fn state(&self) -> PcmState {
+        self.state
+    }
+
+    /// Returns the hardware configuration, if the hardware parameters are set.
+    pub(super) fn config(&self) -> Option<&HwConfig> {
+        self.setup.as_ref().map(|setup| &setup.config)
+    }
+
+    /// Returns the ring buffer, if the hardware parameters are set.
+    pub(super) fn vmo(&self) -> Option<&Arc<Vmo>> {
+        self.setup.as_ref().map(|setup| &setup.vmo)
+    }
+
+    pub(super) fn set_tstamp_type(&mut self, tstamp_type: TstampType) {
+        self.tstamp_type = tstamp_type;
+    }
+
+    pub(super) fn set_hw_params(&mut self, config: HwConfig) -> Result<()> {
+        if !matches!(
+            self.state,
+            PcmState::Open | PcmState::Setup | PcmState::Prepared
+        ) {
+            return_errno_with_message!(Errno::EBADFD, "the PCM stream is busy");
+        }
+
+        self.release_host()?;
+        self.setup = None;
+        self.state = PcmState::Open;
+
+        let params = PcmParams {
+            buffer_bytes: config.buffer_bytes() as u32,
+            period_bytes: config.period_bytes() as u32,
+            channels: config.channels as u8,
+            format: config.virtio_format,
+            rate: config.virtio_rate,
+        };
+        self.sound.set_params(self.stream_id, &params)?;
+
+        let vmo = VmoOptions::new(config.buffer_bytes().align_up(PAGE_SIZE)).alloc()?;
+        let free_buffers = (0..config.buffer_size / config.period_size)
+            .map(|_| PcmBuffer::new(self.stream_id, config.period_bytes()).map(Arc::new))
+            .collect::<core::result::Result<Vec<_>, _>>()?;
+
+        // This is how Linux (and `alsa-lib`) computes the boundary.
+        let mut boundary = config.buffer_size;
+        while boundary * 2 <= isize::MAX as usize - config.buffer_size {
+            boundary *= 2;
+        }
+
+        self.setup = Some(Setup {
+            config,
+            sw: SwConfig {
+                avail_min: config.period_size,
+                start_threshold: 1,
+                stop_threshold: config.buffer_size,
+            },
+            boundary,
+            vmo,
+            hw_ptr: 0,
+            appl_ptr: 0,
+            free_buffers,
+            pending: VecDeque::new(),
+            pending_frames: 0,
+        });
+        self.state = PcmState::Setup;
+
+        Ok(())
+    }
+
+    pub(super) fn free_hw_params(&mut self) -> Result<()> {
+        if !matches!(
+            self.state,
+            PcmState::Open | PcmState::Setup | PcmState::Prepared
+        ) {
+            return_errno_with_message!(Errno::EBADFD, "the PCM stream is busy");
+        }
+
+        self.release_host()?;
+        self.setup = None;
+        self.state = PcmState::Open;
+
+        Ok(())
+    }
+
+    /// Sets the software parameters and returns the boundary.
+    pub(super) fn set_sw_params(&mut self, sw: SwConfig) -> Result<usize> {
+        let setup = self.setup_mut()?;
+        if sw.avail_min == 0 {
+            return_errno_with_message!(Errno::EINVAL, "the minimum available frames are zero");
+        }
+
+        setup.sw = sw;
+        Ok(setup.boundary)
+    }
+
+    pub(super) fn prepare(&mut self) -> Result<()> {
+        match self.state {
+            PcmState::Open => {
+                return_errno_with_message!(Errno::EBADFD, "the hardware parameters are not set")
+            }
+            PcmState::Running | PcmState::Draining | PcmState::Paused => {
+                return_errno_with_message!(Errno::EBUSY, "the PCM stream is running")
+            }
+            PcmState::Setup | PcmState::Prepared | PcmState::Xrun => (),
+        }
+
+        // The stream must be released before it can be prepared again.
+        self.release_host()?;
+        self.sound.prepare(self.stream_id)?;
+        self.is_host_prepared = true;
+
+        let setup = self.setup.as_mut().unwrap();
+        setup.mark_stale();
+        setup.hw_ptr = 0;
+        setup.appl_ptr = 0;
+        self.state = PcmState::Prepared;
+
+        Ok(())
+    }
+
+    pub(super) fn start(&mut self) -> Result<()> {
+        if self.state != PcmState::Prepared {
+            return_errno_with_message!(Errno::EBADFD, "the PCM stream is not prepared");
+        }
+
+        let setup = self.setup.as_mut().unwrap();
+        // The device should have the messages before the stream is started.
+        match self.direction {
+            PcmDirection::Output => {
+                if setup.queued_frames(self.direction) == 0 {
+                    return_errno_with_message!(Errno::EPIPE, "there are no frames to play");
+                }
+                setup.submit_playback(&self.sound, false);
+            }
+            PcmDirection::Input => setup.submit_capture(&self.sound),
+        }
+
+        if let Err(err) = self.sound.start(self.stream_id) {
+            setup.mark_stale();
+            return Err(err.into());
+        }
+        self.state = PcmState::Running;
+        self.trigger_tstamp = self.tstamp_type.now();
+
+        Ok(())
+    }
+
+    /// Stops the stream immediately, which is what `SNDRV_PCM_IOCTL_DROP` does.
+    pub(super) fn drop_frames(&mut self) -> Result<()> {
+        match self.state {
+            PcmState::Open => {
+                return_errno_with_message!(Errno::EBADFD, "the hardware parameters are not set")
+            }
+            PcmState::Setup => (),
+            _ => self.stop(PcmState::Setup),
+        }
+
+        Ok(())
+    }
+
+    /// Starts draining the stream.
+    ///
+    /// The playback streams are drained when all the frames are played, after which they are
+    /// stopped. The capture streams are stopped immediately, so the frames that are not read are
+    /// dropped.
+    pub(super) fn start_drain(&mut self) -> Result<()> {
+        match (self.direction, self.state) {
+            (_, PcmState::Open) => {
+                return_errno_with_message!(Errno::EBADFD, "the hardware parameters are not set")
+            }
+            (_, PcmState::Setup) | (_, PcmState::Draining) => return Ok(()),
+            (_, PcmState::Xrun) | (PcmDirection::Input, _) => {
+                self.stop(PcmState::Setup);
+                return Ok(());
+            }
+            (PcmDirection::Output, PcmState::Prepared) => {
+                let setup = self.setup.as_ref().unwrap();
+                if setup.queued_frames(self.direction) == 0 {
+                    self.state = PcmState::Setup;
+                    return Ok(());
+                }
+                self.start()?;
+            }
+            (PcmDirection::Output, PcmState::Paused) => {
+                self.sound.start(self.stream_id)?;
+            }
+            (PcmDirection::Output, PcmState::Running) => (),
+        }
+
+        self.state = PcmState::Draining;
+        let setup = self.setup.as_mut().unwrap();
+        setup.submit_playback(&self.sound, true);
+        self.check_drained();
+
+        Ok(())
+    }
+
+    pub(super) fn is_draining(&self) -> bool {
+        self.state == PcmState::Draining
+    }
+
+    pub(super) fn pause(&mut self, is_paused: bool) -> Result<()> {
+        match (self.state, is_paused) {
+            (PcmState::Running, true) => {
+                self.sound.stop(self.stream_id)?;
+                self.state = PcmState::Paused;
+            }
+            (PcmState::Paused, false) => {
+                self.sound.start(self.stream_id)?;
+                self.state = PcmState::Running;
+            }
+            _ => return_errno_with_message!(Errno::EBADFD, "the PCM stream cannot be paused"),
+        }
+
+        Ok(())
+    }
+
+    /// Drops the frames between the two pointers, which is what `SNDRV_PCM_IOCTL_RESET` does.
+    pub(super) fn reset(&mut self) -> Result<()> {
+        if !matches!(
+            self.state,
+            PcmState::Prepared | PcmState::Running | PcmState::Paused
+        ) {
+            return_errno_with_message!(Errno::EBADFD, "the PCM stream cannot be reset");
+        }
+
+        let setup = self.setup.as_mut().unwrap();
+        setup.appl_ptr = setup.hw_ptr;
+
+        Ok(())
+    }
+
+    pub(super) fn status(&self) -> PcmStatus {
+        let tstamp = self.tstamp_type.now();
+        let Some(setup) = self.setup.as_ref() else {
+            return PcmStatus {
+                state: self.state,
+                hw_ptr: 0,
+                appl_ptr: 0,
+                avail: 0,
+                delay: 0,
+                avail_min: 1,
+                trigger_tstamp: self.trigger_tstamp,
+                tstamp,
+            };
+        };
+
+        let avail = setup.avail(self.direction);
+        let delay = match self.direction {
+            PcmDirection::Output => setup.config.buffer_size - avail,
+            PcmDirection::Input => avail,
+        };
+        PcmStatus {
+            state: self.state,
+            hw_ptr: setup.hw_ptr,
+            appl_ptr: setup.appl_ptr,
+            avail,
+            delay,
+            avail_min: setup.sw.avail_min,
+            trigger_tstamp: self.trigger_tstamp,
+            tstamp,
+        }
+    }
+
+    /// Updates the application pointer, which is moved by the user space after accessing the
+    /// mapped ring buffer.
+    pub(super) fn set_appl_ptr(&mut self, appl_ptr: usize) -> Result<()> {
+        let direction = self.direction;
+        let state = self.state;
+        let setup = self.setup_mut()?;
+        if appl_ptr >= setup.boundary {
+            return_errno_with_message!(Errno::EINVAL, "the application pointer is out of bounds");
+        }
+
+        let old_appl_ptr = setup.appl_ptr;
+        setup.appl_ptr = appl_ptr;
+        if setup.avail(direction) > setup.config.buffer_size {
+            setup.appl_ptr = old_appl_ptr;
+            return_errno_with_message!(Errno::EINVAL, "the application pointer is out of range");
+        }
+
+        if direction == PcmDirection::Output
+            && matches!(state, PcmState::Running | PcmState::Draining)
+        {
+            setup.submit_playback(&self.sound, state == PcmState::Draining);
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn set_avail_min(&mut self, avail_min: usize) -> Result<()> {
+        let setup = self.setup_mut()?;
+        setup.sw.avail_min = avail_min.max(1);
+        Ok(())
+    }
+
+    /// Writes the frames to the ring buffer without blocking.
+    ///
+    /// On success, the number of the written frames is returned.
+    pub(super) fn write_frames(&mut self, reader: &mut VmReader) -> Result<usize> {
+        match self.state {
+            PcmState::Xrun => return_errno_with_message!(Errno::EPIPE, "an underrun occurs"),
+            PcmState::Prepared | PcmState::Running | PcmState::Paused => (),
+            _ => return_errno_with_message!(Errno::EBADFD, "the PCM stream is not prepared"),
+        }
+
+        let direction = self.direction;
+        let setup = self.setup.as_mut().unwrap();
+        let frame_bytes = setup.config.frame_bytes;
+        let avail = setup.avail(direction);
+        if avail == 0 {
+            return_errno_with_message!(Errno::EAGAIN, "the ring buffer is full");
+        }
+
+        let offset = setup.appl_ptr % setup.config.buffer_size;
+        let frames = (reader.remain() / frame_bytes)
+            .min(avail)
+            .min(setup.config.buffer_size - offset);
+        let mut data = vec![0u8; frames * frame_bytes];
+        reader.read_fallible(&mut VmWriter::from(data.as_mut_slice()).to_fallible())?;
+        setup.vmo.write_bytes(offset * frame_bytes, &data)?;
+        setup.appl_ptr = (setup.appl_ptr + frames) % setup.boundary;
+
+        match self.state {
+            PcmState::Prepared if setup.queued_frames(direction) >= setup.sw.start_threshold => {
+                self.start()?
+            }
+            PcmState::Running => setup.submit_playback(&self.sound, false),
+            _ => (),
+        }
+
+        Ok(frames)
+    }
+
+    /// Reads the frames from the ring buffer without blocking.
+    ///
+    /// On success, the number of the read frames is returned.
+    pub(super) fn read_frames(&mut self, writer: &mut VmWriter) -> Result<usize> {
+        match self.state {
+            PcmState::Xrun => return_errno_with_message!(Errno::EPIPE, "an overrun occurs"),
+            PcmState::Prepared | PcmState::Running | PcmState::Paused => (),
+            _ => return_errno_with_message!(Errno::EBADFD, "the PCM stream is not prepared"),
+        }
+
+        let direction = self.direction;
+        let setup = self.setup.as_mut().unwrap();
+        let frame_bytes = setup.config.frame_bytes;
+        if self.state == PcmState::Prepared
+            && writer.avail() / frame_bytes >= setup.sw.start_threshold
+        {
+            self.start()?;
+            return_errno_with_message!(Errno::EAGAIN, "the capture has just started");
+        }
+
+        let avail = setup.avail(direction);
+        if avail == 0 {
+            return_errno_with_message!(Errno::EAGAIN, "the ring buffer is empty");
+        }
+
+        let offset = setup.appl_ptr % setup.config.buffer_size;
+        let frames = (writer.avail() / frame_bytes)
+            .min(avail)
+            .min(setup.config.buffer_size - offset);
+        let mut data = vec![0u8; frames * frame_bytes];
+        setup.vmo.read_bytes(offset * frame_bytes, &mut data)?;
+        writer.write_fallible(&mut VmReader::from(data.as_slice()).to_fallible())?;
+        setup.appl_ptr = (setup.appl_ptr + frames) % setup.boundary;
+
+        Ok(frames)
+    }
+
+    /// Handles the completion of an I/O message.
+    pub(super) fn complete(
+        &mut self,
+        buffer: Arc<PcmBuffer>,
+        result: core::result::Result<usize, SoundError>,
+    ) {
+        let direction = self.direction;
+        // The messages that are submitted with the old hardware parameters are just dropped.
+        let Some(setup) = self.setup.as_mut() else {
+            return;
+        };
+        let Some(index) = setup
+            .pending
+            .iter()
+            .position(|xfer| Arc::ptr_eq(&xfer.buffer, &buffer))
+        else {
+            return;
+        };
+
+        let xfer = setup.pending.remove(index).unwrap();
+        if xfer.is_stale {
+            setup.free_buffers.push(xfer.buffer);
+            return;
+        }
+        setup.pending_frames -= xfer.frames;
+
+        let frames = match result {
+            Ok(captured_bytes) => match direction {
+                PcmDirection::Output => xfer.frames,
+                PcmDirection::Input => captured_bytes / setup.config.frame_bytes,
+            },
+            Err(err) => {
+                warn!("the I/O message of the PCM stream fails: {:?}", err);
+                setup.free_buffers.push(xfer.buffer);
+                self.stop(PcmState::Xrun);
+                return;
+            }
+        };
+
+        if direction == PcmDirection::Input
+            && setup.avail(direction) + frames > setup.config.buffer_size
+        {
+            setup.free_buffers.push(xfer.buffer);
+            self.stop(PcmState::Xrun);
+            return;
+        }
+        if direction == PcmDirection::Input {
+            setup.copy_captured(&xfer.buffer, frames);
+        }
+        setup.hw_ptr = (setup.hw_ptr + frames) % setup.boundary;
+        setup.free_buffers.push(xfer.buffer);
+
+        match self.state {
+            PcmState::Running if setup.avail(direction) >= setup.sw.stop_threshold => {
+                self.stop(PcmState::Xrun);
+            }
+            PcmState::Running => match direction {
+                PcmDirection::Output => setup.submit_playback(&self.sound, false),
+                PcmDirection::Input => setup.submit_capture(&self.sound),
+            },
+            PcmState::Draining => {
+                setup.submit_playback(&self.sound, true);
+                self.check_drained();
+            }
+            _ => (),
+        }
+    }
+
+    pub(super) fn io_events(&self) -> IoEvents {
+        let (ready, error) = match self.direction {
+            PcmDirection::Output => (IoEvents::OUT, IoEvents::OUT | IoEvents::ERR),
+            PcmDirection::Input => (IoEvents::IN, IoEvents::IN | IoEvents::ERR),
+        };
+
+        match self.state {
+            PcmState::Prepared | PcmState::Running | PcmState::Paused => {
+                let setup = self.setup.as_ref().unwrap();
+                if setup.avail(self.direction) >= setup.sw.avail_min {
+                    ready
+                } else {
+                    IoEvents::empty()
+                }
+            }
+            PcmState::Draining => IoEvents::empty(),
+            PcmState::Open | PcmState::Setup | PcmState::Xrun => error,
+        }
+    }
+
+    /// Stops the stream and frees the resources, which happens when the file is closed.
+    pub(super) fn close(&mut self) {
+        if matches!(self.state, PcmState::Running | PcmState::Draining) {
+            self.stop(PcmState::Setup);
+        }
+        if let Err(err) = self.release_host() {
+            warn!("failed to release the PCM stream: {:?}", err);
+        }
+        self.setup = None;
+        self.state = PcmState::Open;
+        self.tstamp_type = TstampType::GetTimeOfDay;
+    }
+
+    /// Stops the stream if it is running and then changes the state.
+    fn stop(&mut self, new_state: PcmState) {
+        if matches!(self.state, PcmState::Running | PcmState::Draining)
+            && let Err(err) = self.sound.stop(self.stream_id)
+        {
+            warn!("failed to stop the PCM stream: {:?}", err);
+        }
+
+        if let Some(setup) = self.setup.as_mut() {
+            setup.mark_stale();
+        }
+        self.state = new_state;
+    }
+
+    fn check_drained(&mut self) {
+        let setup = self.setup.as_ref().unwrap();
+        if self.state == PcmState::Draining && setup.queued_frames(self.direction) == 0 {
+            self.stop(PcmState::Setup);
+        }
+    }
+
+    /// Releases the stream on the host, after which all the pending messages are completed.
+    fn release_host(&mut self) -> Result<()> {
+        if !self.is_host_prepared {
+            return Ok(());
+        }
+
+        self.sound.release(self.stream_id)?;
+        self.is_host_prepared = false;
+
+        Ok(())
+    }
+
+    fn setup_mut(&mut self) -> Result<&mut Setup> {
+        self.setup.as_mut().ok_or_else(|| {
+            Error::with_message(Errno::EBADFD, "the hardware parameters are not set")
+        })
+    }
+}
+
+impl Setup {
+    /// Returns the number of frames that can be written (for playback) or read (for capture).
+    fn avail(&self, direction: PcmDirection) -> usize {
+        let avail = match direction {
+            PcmDirection::Output => {
+                self.hw_ptr as isize + self.config.buffer_size as isize - self.appl_ptr as isize
+            }
+            PcmDirection::Input => self.hw_ptr as isize - self.appl_ptr as isize,
+        };
+
+        if avail < 0 {
+            (avail + self.boundary as isize) as usize
+        } else if avail as usize >= self.boundary {
+            avail as usize - self.boundary
+        } else {
+            avail as usize
+        }
+    }
+
+    /// Returns the number of frames between the two pointers.
+    fn queued_frames(&self, direction: PcmDirection) -> usize {
+        match direction {
+            PcmDirection::Output => self
+                .config
+                .buffer_size
+                .saturating_sub(self.avail(direction)),
+            PcmDirection::Input => self.avail(direction),
+        }
+    }
+
+    /// Submits the periods that are written but not submitted.
+    ///
+    /// If the stream is draining, the last partial period is submitted as well, which is padded
+    /// with zeros.
+    fn submit_playback(&mut self, sound: &SoundDevice, is_draining: bool) {
+        let frame_bytes = self.config.frame_bytes;
+
+        loop {
+            let ready = self
+                .queued_frames(PcmDirection::Output)
+                .saturating_sub(self.pending_frames);
+            let frames = if ready >= self.config.period_size {
+                self.config.period_size
+            } else if is_draining && ready > 0 {
+                ready
+            } else {
+                break;
+            };
+            let Some(buffer) = self.free_buffers.pop() else {
+                break;
+            };
+
+            let mut data = vec![0u8; self.config.period_bytes()];
+            let start = (self.hw_ptr + self.pending_frames) % self.config.buffer_size;
+            let first_frames = frames.min(self.config.buffer_size - start);
+            let (first, second) =
+                data[..frames * frame_bytes].split_at_mut(first_frames * frame_bytes);
+            if let Err(err) = self
+                .vmo
+                .read_bytes(start * frame_bytes, first)
+                .and_then(|_| self.vmo.read_bytes(0, second))
+            {
+                warn!("failed to read the PCM ring buffer: {:?}", err);
+            }
+            buffer.write_data(0, &data);
+
+            if let Err(err) = sound.submit_playback(buffer.clone(), data.len()) {
+                warn!("failed to submit the PCM frames: {:?}", err);
+                self.free_buffers.push(buffer);
+                break;
+            }
+            self.pending.push_back(PendingXfer {
+                buffer,
+                frames,
+                is_stale: false,
+            });
+            self.pending_frames += frames;
+        }
+    }
+
+    /// Submits all the free buffers to be filled with the captured frames.
+    fn submit_capture(&mut self, sound: &SoundDevice) {
+        while let Some(buffer) = self.free_buffers.pop() {
+            if let Err(err) = sound.submit_capture(buffer.clone()) {
+                warn!("failed to submit the PCM buffer: {:?}", err);
+                self.free_buffers.push(buffer);
+                break;
+            }
+            self.pending.push_back(PendingXfer {
+                buffer,
+                frames: self.config.period_size,
+                is_stale: false,
+            });
+            self.pending_frames += self.config.period_size;
+        }
+    }
+
+    /// Copies the captured frames to the ring buffer at the hardware pointer.
+    fn copy_captured(&self, buffer: &PcmBuffer, frames: usize) {
+        let frame_bytes = self.config.frame_bytes;
+
+        let mut data = vec![0u8; frames * frame_bytes];
+        buffer.read_data(0, &mut data);
+
+        let start = self.hw_ptr % self.config.buffer_size;
+        let first_frames = frames.min(self.config.buffer_size - start);
+        let (first, second) = data.split_at(first_frames * frame_bytes);
+        if let Err(err) = self
+            .vmo
+            .write_bytes(start * frame_bytes, first)
+            .and_then(|_| self.vmo.write_bytes(0, second))
+        {
+            warn!("failed to write the PCM ring buffer: {:?}", err);
+        }
+    }
+
+    /// Marks the pending messages as stale, so their completions no longer move the pointers.
+    fn mark_stale(&mut self) {
+        for xfer in self.pending.iter_mut() {
+            xfer.is_stale = true;
+        }
+        self.pending_frames = 0;
+    }
+}
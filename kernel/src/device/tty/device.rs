@@ -15,8 +15,8 @@ use crate::{
         registry::char,
         tty::{
             Tty,
-            hvc::hvc0_device,
-            serial::serial0_device,
+            hvc::hvc_device,
+            serial::serial_device,
             vt::{VtDriver, tty1_device},
         },
     },
@@ -49,6 +49,10 @@ impl Device for Tty0Device {
         Some("tty0".into())
     }
 
+    fn class(&self) -> &'static str {
+        "tty"
+    }
+
     fn open(&self) -> Result<Box<dyn FileIo>> {
         self.active_vt().open()
     }
@@ -77,6 +81,10 @@ impl Device for TtyDevice {
         Some("tty".into())
     }
 
+    fn class(&self) -> &'static str {
+        "tty"
+    }
+
     fn open(&self) -> Result<Box<dyn FileIo>> {
         let Some(terminal) = current!().terminal() else {
             return_errno_with_message!(
@@ -104,14 +112,25 @@ impl SystemConsole {
             // TODO: Support specifying multiple TTY devices, e.g., "console=hvc0 console=tty0".
             let console_name = CONSOLES
                 .get()
-                .and_then(|consoles| consoles.first().map(|s| s.as_str()))
+                .and_then(|consoles| consoles.first())
+                .map(|console| split_console_options(console).0)
                 .unwrap_or("tty0");
 
-            let device = match console_name {
-                "tty0" => Some(Arc::new(Tty0Device) as _),
-                "ttyS0" => serial0_device().cloned().map(|device| device as _),
-                "hvc0" => hvc0_device().cloned().map(|device| device as _),
-                _ => None,
+            let device = if console_name == "tty0" {
+                Some(Arc::new(Tty0Device) as _)
+            } else if let Some(index) = console_name.strip_prefix("hvc") {
+                index
+                    .parse()
+                    .ok()
+                    .and_then(hvc_device)
+                    .map(|device| device as _)
+            } else {
+                console_name
+                    .strip_prefix("ttyS")
+                    .and_then(|port_num| port_num.parse().ok())
+                    .and_then(serial_device)
+                    .cloned()
+                    .map(|device| device as _)
             };
             let inner = device.unwrap_or_else(|| {
                 warn!(
@@ -144,6 +163,10 @@ impl Device for SystemConsole {
         Some("console".into())
     }
 
+    fn class(&self) -> &'static str {
+        "tty"
+    }
+
     fn open(&self) -> Result<Box<dyn FileIo>> {
         self.inner.open()
     }
@@ -157,5 +180,24 @@ pub(super) fn init_in_first_process() -> Result<()> {
     Ok(())
 }
 
+/// Returns the options of the console specified in the kernel command line.
+///
+/// For example, if "console=ttyS0,115200n8" is specified, the options of the `ttyS0` console are
+/// "115200n8".
+pub(super) fn console_options(name: &str) -> Option<&'static str> {
+    CONSOLES.get()?.iter().find_map(|console| {
+        let (console_name, options) = split_console_options(console);
+        (console_name == name).then_some(options?)
+    })
+}
+
+/// Splits a console specified in the kernel command line into the name and the options.
+fn split_console_options(console: &str) -> (&str, Option<&str>) {
+    match console.split_once(',') {
+        Some((name, options)) => (name, Some(options)),
+        None => (console, None),
+    }
+}
+
 static CONSOLES: Once<Vec<String>> = Once::new();
 aster_cmdline::define_repeatable_kv_param!("console", CONSOLES);
@@ -54,11 +54,44 @@ pub trait TtyDriver: Send + Sync + 'static {
     /// [`Tty::can_push`]: super::Tty::can_push
     fn notify_input(&self);
 
+    /// Returns the initial termios of the TTY.
+    fn init_termios(&self) -> CTermios {
+        CTermios::default()
+    }
+
     /// Notifies that the TTY termios is changed.
     ///
     /// This method will be called with a spin lock held, so it cannot break atomic mode.
     fn on_termios_change(&self, old_termios: &CTermios, new_termios: &CTermios);
 
+    /// Notifies that the output is stopped or restarted by the flow control.
+    ///
+    /// This method will be called with a spin lock held, so it cannot break atomic mode.
+    fn on_flow_change(&self, _is_stopped: bool) {}
+
+    /// Notifies that the input is throttled or unthrottled.
+    ///
+    /// The input is throttled if the input buffer is almost full. If the input flow control is
+    /// enabled (i.e., `IXOFF` is set), the driver should ask the device to stop sending characters
+    /// until the input is unthrottled. Drivers whose input cannot be lost (e.g., the
+    /// pseudoterminal driver) can simply ignore this.
+    ///
+    /// This method will be called with a spin lock held, so it cannot break atomic mode.
+    fn on_throttle_change(&self, _is_throttled: bool, _termios: &CTermios) {}
+
+    /// Sends a high-priority character (e.g., `VSTART` or `VSTOP`) to the output.
+    ///
+    /// The character should be sent even if the output is stopped by the flow control.
+    fn send_xchar(&self, ch: u8) {
+        let _ = self.push_output(&[ch]);
+    }
+
+    /// Discards the characters in the output buffer.
+    fn flush_output(&self) {}
+
+    /// Notifies that the characters in the input buffer have been discarded.
+    fn on_input_flush(&self) {}
+
     /// Handles driver-specific ioctl.
     ///
     /// This method allows a TTY driver to handle driver-specific
@@ -3,8 +3,8 @@
 use alloc::{boxed::Box, format, sync::Arc};
 
 use aster_console::AnyConsoleDevice;
+use aster_virtio::device::console::{self, ConsolePort};
 use ostd::mm::{Infallible, VmReader, VmWriter};
-use spin::Once;
 
 use super::{Tty, TtyDriver};
 use crate::{
@@ -14,6 +14,7 @@ use crate::{
     },
     fs::file::FileIo,
     prelude::*,
+    thread::work_queue::{WorkPriority, submit_work_item, work_item::WorkItem},
 };
 
 /// The driver for hypervisor console devices.
@@ -52,42 +53,70 @@ impl TtyDriver for HvcDriver {
     fn on_termios_change(&self, _old_termios: &CTermios, _new_termios: &CTermios) {}
 }
 
-static HVC0: Once<Arc<Tty<HvcDriver>>> = Once::new();
+/// The `hvc<N>` devices and the virtio console ports that they are backed by.
+static HVCS: Mutex<Vec<(Arc<ConsolePort>, Arc<Tty<HvcDriver>>)>> = Mutex::new(Vec::new());
 
-/// Returns the `hvc0` device.
+/// Returns the `hvc<index>` device.
 ///
 /// Returns `None` if the device is not found nor initialized.
-pub(super) fn hvc0_device() -> Option<&'static Arc<Tty<HvcDriver>>> {
-    HVC0.get()
+pub(super) fn hvc_device(index: u32) -> Option<Arc<Tty<HvcDriver>>> {
+    HVCS.lock().get(index as usize).map(|(_, hvc)| hvc.clone())
+}
+
+/// Adds an `hvc<N>` device for the virtio console port if the port is a console port.
+///
+/// Like Linux, the `hvc<N>` devices are numbered in the order in which the console ports are
+/// found.
+fn add_console_port(port: &Arc<ConsolePort>) -> Result<()> {
+    if !port.is_console() {
+        return Ok(());
+    }
+
+    let mut hvcs = HVCS.lock();
+    if hvcs.iter().any(|(hvc_port, _)| Arc::ptr_eq(hvc_port, port)) {
+        return Ok(());
+    }
+
+    let driver = HvcDriver {
+        console: port.clone(),
+    };
+    let hvc = Tty::new(hvcs.len() as u32, driver);
+
+    char::register(hvc.clone())?;
+    hvcs.push((port.clone(), hvc.clone()));
+
+    port.register_callback(Box::leak(Box::new(
+        move |mut reader: VmReader<Infallible>| {
+            let mut chs = vec![0u8; reader.remain()];
+            reader.read(&mut VmWriter::from(chs.as_mut_slice()));
+            let _ = hvc.push_input(chs.as_slice());
+        },
+    )));
+
+    Ok(())
 }
 
 pub(super) fn init_in_first_process() -> Result<()> {
-    let devices = aster_console::all_devices();
-
-    // Initialize the `hvc0` device if the virtio console is available.
-
-    let virtio_console = devices
-        .iter()
-        .find(|(name, _)| name.as_str() == aster_virtio::device::console::DEVICE_NAME)
-        .map(|(_, device)| device.clone());
-
-    if let Some(virtio_console) = virtio_console {
-        let driver = HvcDriver {
-            console: virtio_console.clone(),
-        };
-        let hvc0 = Tty::new(0, driver);
-
-        HVC0.call_once(|| hvc0.clone());
-        char::register(hvc0.clone())?;
-
-        virtio_console.register_callback(Box::leak(Box::new(
-            move |mut reader: VmReader<Infallible>| {
-                let mut chs = vec![0u8; reader.remain()];
-                reader.read(&mut VmWriter::from(chs.as_mut_slice()));
-                let _ = hvc0.push_input(chs.as_slice());
-            },
-        )));
+    // Add the console ports that are already found, so that they can be used as the system
+    // console. The console ports found later are added in the work queue, since registering a
+    // char device cannot be done in the interrupt context.
+    for port in console::all_ports() {
+        add_console_port(&port)?;
     }
 
+    console::register_port_handler(|port: &Arc<ConsolePort>| {
+        if !port.is_console() {
+            return;
+        }
+
+        let port = port.clone();
+        let work_item = WorkItem::new(Box::new(move || {
+            if let Err(err) = add_console_port(&port) {
+                warn!("failed to add the virtio console port: {:?}", err);
+            }
+        }));
+        submit_work_item(work_item, WorkPriority::High);
+    });
+
     Ok(())
 }
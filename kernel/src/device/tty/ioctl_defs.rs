@@ -1,7 +1,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use super::termio::{CTermios, CWinSize};
-use crate::util::ioctl::{InData, OutData, ioc};
+use crate::util::ioctl::{InData, OutData, PassByVal, ioc};
 
 // Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/asm-generic/ioctls.h>
 
@@ -10,5 +10,28 @@ pub type SetTermios      = ioc!(TCSETS,     0x5402,     InData<CTermios>);
 pub type SetTermiosWait  = ioc!(TCSETSW,    0x5403,     InData<CTermios>);
 pub type SetTermiosFlush = ioc!(TCSETSF,    0x5404,     InData<CTermios>);
 
+pub type SendBreak       = ioc!(TCSBRK,     0x5409,     InData<i32, PassByVal>);
+pub type FlowControl     = ioc!(TCXONC,     0x540A,     InData<i32, PassByVal>);
+pub type FlushQueue      = ioc!(TCFLSH,     0x540B,     InData<i32, PassByVal>);
+
 pub type GetWinSize      = ioc!(TIOCGWINSZ, 0x5413,     OutData<CWinSize>);
 pub type SetWinSize      = ioc!(TIOCSWINSZ, 0x5414,     InData<CWinSize>);
+
+pub type GetModemLines   = ioc!(TIOCMGET,   0x5415,     OutData<u32>);
+pub type SetModemBits    = ioc!(TIOCMBIS,   0x5416,     InData<u32>);
+pub type ClearModemBits  = ioc!(TIOCMBIC,   0x5417,     InData<u32>);
+pub type SetModemLines   = ioc!(TIOCMSET,   0x5418,     InData<u32>);
+
+pub type SetLdisc        = ioc!(TIOCSETD,   0x5423,     InData<i32>);
+pub type GetLdisc        = ioc!(TIOCGETD,   0x5424,     OutData<i32>);
+
+// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/asm-generic/termbits-common.h>
+
+pub(super) const TCOOFF: i32 = 0;
+pub(super) const TCOON: i32 = 1;
+pub(super) const TCIOFF: i32 = 2;
+pub(super) const TCION: i32 = 3;
+
+pub(super) const TCIFLUSH: i32 = 0;
+pub(super) const TCOFLUSH: i32 = 1;
+pub(super) const TCIOFLUSH: i32 = 2;
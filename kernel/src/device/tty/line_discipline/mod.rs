@@ -0,0 +1,351 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use self::n_tty::NTty;
+use super::termio::{CTermios, CWinSize};
+use crate::{prelude::*, process::signal::sig_num::SigNum};
+
+mod n_tty;
+
+// This implementation references the implementation of Linux:
+// <https://elixir.bootlin.com/linux/v6.18/source/include/linux/tty_ldisc.h>
+// <https://elixir.bootlin.com/linux/v6.18/source/drivers/tty/tty_ldisc.c>
+
+// The input is throttled if the room in the buffer is less than `THROTTLE_THRESHOLD`, and
+// unthrottled if the characters in the buffer are fewer than `UNTHROTTLE_THRESHOLD`.
+//
+// Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/tty/n_tty.c>.
+const THROTTLE_THRESHOLD: usize = 128;
+const UNTHROTTLE_THRESHOLD: usize = 128;
+
+/// A line discipline.
+///
+/// A line discipline processes the characters received from the TTY driver before they can be
+/// read by the user space. The default line discipline is N_TTY, which implements the terminal
+/// semantics defined by the termios (e.g., line editing, echoing, and signal characters).
+///
+/// Other line disciplines (e.g., SLIP and PPP) can consume the received characters by themselves
+/// and attach the TTY to other subsystems. They should be created in [`LdiscId::new_ldisc`].
+pub(super) trait LineDiscipline: Send {
+    /// Returns the ID of the line discipline.
+    fn id(&self) -> LdiscId;
+
+    /// Receives a character from the TTY driver.
+    ///
+    /// # Errors
+    ///
+    /// If the character cannot be accepted because the buffer is full, this method returns
+    /// [`Errno::EAGAIN`].
+    fn receive_char(&mut self, ch: u8, ctx: &mut ReceiveContext) -> Result<()>;
+
+    /// Reads bytes from the line discipline to `dst`, returning the actual bytes read.
+    ///
+    /// The number of bytes read can be zero if the end of file is met (e.g., the `VEOF` character
+    /// in the canonical mode).
+    ///
+    /// # Errors
+    ///
+    /// If no input is available, this method returns [`Errno::EAGAIN`].
+    fn try_read(&mut self, dst: &mut [u8], termios: &CTermios) -> Result<usize>;
+
+    /// Returns how a read operation should wait for the input.
+    fn read_policy(&self, termios: &CTermios) -> ReadPolicy;
+
+    /// Returns whether the input is available for polling.
+    fn is_readable(&self, termios: &CTermios) -> bool;
+
+    /// Returns the number of bytes that can be read immediately.
+    fn readable_len(&self, termios: &CTermios) -> usize;
+
+    /// Returns the number of bytes in the buffer, including those that cannot be read yet.
+    fn buffer_len(&self) -> usize;
+
+    /// Returns the capacity of the buffer.
+    fn buffer_capacity(&self) -> usize;
+
+    /// Discards all the characters in the buffer.
+    fn flush_input(&mut self);
+
+    /// Notifies that the termios is changed.
+    fn on_termios_change(&mut self, old_termios: &CTermios, new_termios: &CTermios);
+}
+
+/// A line discipline ID; the `N_*` constants in Linux.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/tty.h>.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+pub(super) enum LdiscId {
+    Tty = 0,
+    Slip = 1,
+    Ppp = 3,
+}
+
+impl LdiscId {
+    /// Creates a new line discipline with the ID.
+    fn new_ldisc(self) -> Result<Box<dyn LineDiscipline>> {
+        match self {
+            Self::Tty => Ok(Box::new(NTty::new())),
+            // TODO: Support SLIP and PPP once we have the corresponding network devices.
+            Self::Slip | Self::Ppp => {
+                return_errno_with_message!(Errno::EINVAL, "the line discipline is not supported")
+            }
+        }
+    }
+}
+
+/// How a read operation should wait for the input.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/tty/n_tty.c>.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct ReadPolicy {
+    /// The number of bytes to read before the read operation completes.
+    pub(super) min_len: usize,
+    /// The timeout for the first byte.
+    ///
+    /// If the timeout is `None`, the read operation blocks until the input is available. If the
+    /// timeout is zero, the read operation never blocks.
+    pub(super) timeout: Option<Duration>,
+    /// The timeout for the following bytes after some bytes have been read.
+    pub(super) inter_byte_timeout: Option<Duration>,
+}
+
+/// The context in which a line discipline receives characters.
+pub(super) struct ReceiveContext<'a> {
+    termios: &'a CTermios,
+    output_flow: &'a mut OutputFlow,
+    signal_callback: &'a mut dyn FnMut(SigNum),
+    echo_callback: &'a mut dyn FnMut(&[u8]),
+    flow_callback: &'a mut dyn FnMut(bool),
+}
+
+impl<'a> ReceiveContext<'a> {
+    /// Returns the termios.
+    pub(super) fn termios(&self) -> &'a CTermios {
+        self.termios
+    }
+
+    /// Sends a signal to the foreground process group.
+    pub(super) fn send_signal(&mut self, signum: SigNum) {
+        (self.signal_callback)(signum);
+    }
+
+    /// Echoes characters to the output.
+    pub(super) fn echo(&mut self, chs: &[u8]) {
+        (self.echo_callback)(chs);
+    }
+
+    /// Stops the output because of the `VSTOP` character.
+    pub(super) fn stop_output(&mut self) {
+        if *self.output_flow != OutputFlow::Running {
+            return;
+        }
+
+        *self.output_flow = OutputFlow::StoppedByChar;
+        (self.flow_callback)(true);
+    }
+
+    /// Restarts the output because of the `VSTART` character (or any character if `IXANY` is
+    /// set).
+    ///
+    /// The output stopped by the `TCOOFF` ioctl will not be restarted.
+    pub(super) fn start_output(&mut self) {
+        if *self.output_flow != OutputFlow::StoppedByChar {
+            return;
+        }
+
+        *self.output_flow = OutputFlow::Running;
+        (self.flow_callback)(false);
+    }
+}
+
+/// The state of the output flow control.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OutputFlow {
+    /// The output is running.
+    Running,
+    /// The output is stopped by the `VSTOP` character.
+    StoppedByChar,
+    /// The output is stopped by the `TCOOFF` ioctl.
+    ///
+    /// In this state, only the `TCOON` ioctl can restart the output.
+    StoppedByIoctl,
+}
+
+/// A TTY's line discipline and the states that it relies on.
+///
+/// The states (e.g., the termios and the window size) belong to the TTY, so they are kept when
+/// the line discipline is changed.
+pub(super) struct TtyLdisc {
+    /// Line discipline
+    ldisc: Box<dyn LineDiscipline>,
+    /// Termios
+    termios: CTermios,
+    /// Window size
+    winsize: CWinSize,
+    /// Output flow
+    output_flow: OutputFlow,
+    /// Whether the input is throttled
+    is_throttled: bool,
+}
+
+impl TtyLdisc {
+    /// Creates a new N_TTY line discipline with the initial termios.
+    pub(super) fn new(termios: CTermios) -> Self {
+        Self {
+            ldisc: Box::new(NTty::new()),
+            termios,
+            winsize: CWinSize::default(),
+            output_flow: OutputFlow::Running,
+            is_throttled: false,
+        }
+    }
+
+    /// Returns the ID of the line discipline.
+    pub(super) fn id(&self) -> LdiscId {
+        self.ldisc.id()
+    }
+
+    /// Changes the line discipline.
+    ///
+    /// Changing the line discipline discards all the characters in the buffer.
+    pub(super) fn set_ldisc(&mut self, id: LdiscId) -> Result<()> {
+        if self.ldisc.id() == id {
+            return Ok(());
+        }
+
+        self.ldisc = id.new_ldisc()?;
+        self.termios.set_line(id as u8);
+
+        Ok(())
+    }
+
+    /// Pushes a character to the line discipline.
+    ///
+    /// The flow callback is called with `true` if the output is stopped, or with `false` if the
+    /// output is restarted.
+    pub(super) fn push_char<F1: FnMut(SigNum), F2: FnMut(&[u8]), F3: FnMut(bool)>(
+        &mut self,
+        ch: u8,
+        mut signal_callback: F1,
+        mut echo_callback: F2,
+        mut flow_callback: F3,
+    ) -> Result<()> {
+        let mut ctx = ReceiveContext {
+            termios: &self.termios,
+            output_flow: &mut self.output_flow,
+            signal_callback: &mut signal_callback,
+            echo_callback: &mut echo_callback,
+            flow_callback: &mut flow_callback,
+        };
+
+        self.ldisc.receive_char(ch, &mut ctx)
+    }
+
+    /// Reads bytes from `self` to `dst`, returning the actual bytes read.
+    ///
+    /// See [`LineDiscipline::try_read`] for details.
+    pub(super) fn try_read(&mut self, dst: &mut [u8]) -> Result<usize> {
+        self.ldisc.try_read(dst, &self.termios)
+    }
+
+    /// Returns how a read operation should wait for the input.
+    pub(super) fn read_policy(&self) -> ReadPolicy {
+        self.ldisc.read_policy(&self.termios)
+    }
+
+    /// Returns whether the input is available for polling.
+    pub(super) fn is_readable(&self) -> bool {
+        self.ldisc.is_readable(&self.termios)
+    }
+
+    /// Returns the number of bytes that can be read immediately.
+    pub(super) fn readable_len(&self) -> usize {
+        self.ldisc.readable_len(&self.termios)
+    }
+
+    /// Discards all the characters in the input buffer.
+    pub(super) fn flush_input(&mut self) {
+        self.ldisc.flush_input();
+    }
+
+    /// Checks whether the input should be throttled or unthrottled.
+    ///
+    /// The input should be throttled if the buffer is almost full, and unthrottled if the buffer
+    /// has enough room again. This method returns the new state if it changes.
+    pub(super) fn check_throttle(&mut self) -> Option<bool> {
+        let len = self.ldisc.buffer_len();
+
+        let should_throttle = if self.is_throttled {
+            len >= UNTHROTTLE_THRESHOLD
+        } else {
+            self.ldisc.buffer_capacity() - len < THROTTLE_THRESHOLD
+        };
+        if should_throttle == self.is_throttled {
+            return None;
+        }
+
+        self.is_throttled = should_throttle;
+        Some(should_throttle)
+    }
+
+    /// Returns whether the output is stopped by the flow control.
+    pub(super) fn is_output_stopped(&self) -> bool {
+        self.output_flow != OutputFlow::Running
+    }
+
+    /// Stops the output because of the `TCOOFF` ioctl.
+    ///
+    /// This method returns whether the output was running before.
+    pub(super) fn stop_output_by_ioctl(&mut self) -> bool {
+        let was_running = self.output_flow == OutputFlow::Running;
+        self.output_flow = OutputFlow::StoppedByIoctl;
+        was_running
+    }
+
+    /// Restarts the output because of the `TCOON` ioctl.
+    ///
+    /// This method returns whether the output was stopped by the `TCOOFF` ioctl before.
+    pub(super) fn start_output_by_ioctl(&mut self) -> bool {
+        if self.output_flow != OutputFlow::StoppedByIoctl {
+            return false;
+        }
+
+        self.output_flow = OutputFlow::Running;
+        true
+    }
+
+    /// Returns whether new characters cannot be pushed because the buffer is full.
+    pub(super) fn is_full(&self) -> bool {
+        self.ldisc.buffer_len() >= self.ldisc.buffer_capacity()
+    }
+
+    pub(super) fn termios(&self) -> &CTermios {
+        &self.termios
+    }
+
+    /// Sets the termios.
+    ///
+    /// The line discipline number (i.e., `c_line`) in `termios` is ignored. Use
+    /// [`Self::set_ldisc`] to change the line discipline.
+    pub(super) fn set_termios(&mut self, mut termios: CTermios) {
+        termios.set_line(self.termios.line());
+
+        self.ldisc.on_termios_change(&self.termios, &termios);
+        self.termios = termios;
+    }
+
+    pub(super) fn window_size(&self) -> CWinSize {
+        self.winsize
+    }
+
+    /// Sets the window size.
+    ///
+    /// This method returns whether the window size is changed.
+    pub(super) fn set_window_size(&mut self, winsize: CWinSize) -> bool {
+        let is_changed = self.winsize != winsize;
+        self.winsize = winsize;
+        is_changed
+    }
+}
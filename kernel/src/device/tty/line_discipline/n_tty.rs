@@ -0,0 +1,728 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use bitvec::{BitArr, bitarr};
+use ostd::const_assert;
+
+use super::{LdiscId, LineDiscipline, ReadPolicy, ReceiveContext};
+use crate::{
+    device::tty::termio::{
+        CCtrlCharId, CInputFlags, CLocalFlags, COutputFlags, CTermios, DISABLED_CHAR,
+    },
+    prelude::*,
+    process::signal::{
+        constants::{SIGINT, SIGQUIT, SIGTSTP},
+        sig_num::SigNum,
+    },
+};
+
+// This implementation references the implementation of Linux:
+// <https://elixir.bootlin.com/linux/v6.18/source/drivers/tty/n_tty.c>
+
+const LINE_CAPACITY: usize = 4095;
+const BUFFER_CAPACITY: usize = 8192;
+
+// `LINE_CAPACITY` must be less than `BUFFER_CAPACITY`. Otherwise, `write()` can be blocked
+// indefinitely if both the current line and the buffer are full, so even the line terminator won't
+// be accepted.
+const_assert!(LINE_CAPACITY < BUFFER_CAPACITY);
+// `BUFFER_CAPACITY` must be a power of two, so the positions can wrap around.
+const_assert!(BUFFER_CAPACITY.is_power_of_two());
+
+/// The N_TTY line discipline.
+///
+/// The received characters are stored in a ring buffer. The characters in the ring buffer are
+/// divided by the following positions (which only increase and wrap around on overflow):
+///
+/// ```text
+///   read_tail       canon_head        read_head
+///       |               |                 |
+///       v               v                 v
+/// ... | complete lines  | current line    | ...
+/// ```
+///
+/// In the canonical mode, only the complete lines can be read, while the current line can be
+/// edited (e.g., by `VERASE`). In the non-canonical mode, all the characters can be read.
+pub(super) struct NTty {
+    /// Ring buffer
+    read_buf: Box<[u8]>,
+    /// Whether the character at each position terminates a line
+    line_ends: BitArr![for BUFFER_CAPACITY],
+    /// Position of the next character to read
+    read_tail: usize,
+    /// Position after the last complete line
+    canon_head: usize,
+    /// Position after the last received character
+    read_head: usize,
+    /// Whether the next character should be received literally (after `VLNEXT`)
+    is_lnext: bool,
+    /// Whether the erased characters are being echoed (with `ECHOPRT`)
+    is_erasing: bool,
+    /// Current cursor column, tracked by the echoed characters
+    column: usize,
+    /// Cursor column when the first character in the current line is echoed
+    canon_column: usize,
+}
+
+/// The kind of erasing in the canonical mode.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum EraseKind {
+    /// Erases the last character (`VERASE`).
+    Char,
+    /// Erases the last word (`VWERASE`).
+    Word,
+    /// Erases the current line (`VKILL`).
+    Line,
+}
+
+impl NTty {
+    pub(super) fn new() -> Self {
+        Self {
+            read_buf: vec![0; BUFFER_CAPACITY].into_boxed_slice(),
+            line_ends: bitarr![0; BUFFER_CAPACITY],
+            read_tail: 0,
+            canon_head: 0,
+            read_head: 0,
+            is_lnext: false,
+            is_erasing: false,
+            column: 0,
+            canon_column: 0,
+        }
+    }
+
+    fn char_at(&self, pos: usize) -> u8 {
+        self.read_buf[pos % BUFFER_CAPACITY]
+    }
+
+    /// Puts a character into the buffer.
+    ///
+    /// The caller must ensure that the buffer is not full.
+    fn put_char(&mut self, ch: u8) {
+        debug_assert!(self.buffer_len() < BUFFER_CAPACITY);
+
+        self.read_buf[self.read_head % BUFFER_CAPACITY] = ch;
+        self.read_head = self.read_head.wrapping_add(1);
+    }
+
+    /// Puts a line terminator into the buffer, making the current line complete.
+    fn put_line_end(&mut self, ch: u8) {
+        self.line_ends.set(self.read_head % BUFFER_CAPACITY, true);
+        self.put_char(ch);
+        self.canon_head = self.read_head;
+    }
+
+    /// Returns the number of characters in the current line.
+    fn line_len(&self) -> usize {
+        self.read_head.wrapping_sub(self.canon_head)
+    }
+
+    /// Receives a character that has no special meaning.
+    fn receive_normal_char(&mut self, ch: u8, ctx: &mut ReceiveContext) {
+        let termios = ctx.termios();
+
+        // Typically, a TTY in raw mode does not echo. But the TTY can also be in a CBREAK mode,
+        // with ICANON closed and ECHO opened.
+        if termios.local_flags().contains(CLocalFlags::ECHO) {
+            self.finish_erasing(ctx);
+            if self.line_len() == 0 {
+                self.canon_column = self.column;
+            }
+            self.echo_char(ch, ctx);
+        }
+
+        // If the line is full, the character will be ignored, but other actions such as echoing
+        // and signaling will work as normal. This will never block the caller, even if the input
+        // comes from the pseduoterminal master.
+        if termios.is_canonical_mode() && self.line_len() >= LINE_CAPACITY {
+            return;
+        }
+
+        self.put_char(ch);
+    }
+
+    /// Receives a character that generates a signal.
+    fn receive_signal_char(&mut self, ch: u8, signum: SigNum, ctx: &mut ReceiveContext) {
+        let termios = ctx.termios();
+
+        if !termios.local_flags().contains(CLocalFlags::NOFLSH) {
+            self.flush_input();
+        }
+        ctx.send_signal(signum);
+
+        if termios.input_flags().contains(CInputFlags::IXON) {
+            ctx.start_output();
+        }
+
+        if termios.local_flags().contains(CLocalFlags::ECHO) {
+            self.echo_char(ch, ctx);
+        }
+    }
+
+    /// Receives a character that has special meanings in the canonical mode.
+    ///
+    /// This method returns `false` if the character has no special meanings.
+    fn receive_canon_char(&mut self, ch: u8, ctx: &mut ReceiveContext) -> bool {
+        let termios = ctx.termios();
+        let local_flags = *termios.local_flags();
+
+        if let Some(kind) = EraseKind::from_char(ch, termios) {
+            self.erase(ch, kind, ctx);
+            return true;
+        }
+
+        if is_char(ch, CCtrlCharId::VLNEXT, termios) && local_flags.contains(CLocalFlags::IEXTEN) {
+            self.is_lnext = true;
+            if local_flags.contains(CLocalFlags::ECHO) {
+                self.finish_erasing(ctx);
+                if local_flags.contains(CLocalFlags::ECHOCTL) {
+                    // Show a `^` and move the cursor back onto it.
+                    self.echo_raw(b'^', ctx);
+                    self.echo_raw(b'\x08', ctx);
+                }
+            }
+            return true;
+        }
+
+        if is_char(ch, CCtrlCharId::VREPRINT, termios)
+            && local_flags.contains(CLocalFlags::ECHO | CLocalFlags::IEXTEN)
+        {
+            self.finish_erasing(ctx);
+            self.echo_char(ch, ctx);
+            self.echo_raw(b'\n', ctx);
+            for i in 0..self.line_len() {
+                self.echo_char(self.char_at(self.canon_head.wrapping_add(i)), ctx);
+            }
+            return true;
+        }
+
+        if ch == b'\n' {
+            if local_flags.intersects(CLocalFlags::ECHO | CLocalFlags::ECHONL) {
+                self.echo_raw(b'\n', ctx);
+            }
+            self.put_line_end(ch);
+            return true;
+        }
+
+        if is_char(ch, CCtrlCharId::VEOF, termios) {
+            // The end-of-file character is not visible to the user space. It is replaced by a
+            // disabled character to mark the end of the line.
+            self.put_line_end(DISABLED_CHAR);
+            return true;
+        }
+
+        if is_char(ch, CCtrlCharId::VEOL, termios)
+            || (is_char(ch, CCtrlCharId::VEOL2, termios)
+                && local_flags.contains(CLocalFlags::IEXTEN))
+        {
+            if local_flags.contains(CLocalFlags::ECHO) {
+                if self.line_len() == 0 {
+                    self.canon_column = self.column;
+                }
+                self.echo_char(ch, ctx);
+            }
+            self.put_line_end(ch);
+            return true;
+        }
+
+        false
+    }
+
+    /// Erases characters in the current line.
+    fn erase(&mut self, ch: u8, kind: EraseKind, ctx: &mut ReceiveContext) {
+        let termios = ctx.termios();
+        let local_flags = *termios.local_flags();
+
+        if self.line_len() == 0 {
+            return;
+        }
+
+        if kind == EraseKind::Line {
+            if !local_flags.contains(CLocalFlags::ECHO) {
+                self.read_head = self.canon_head;
+                return;
+            }
+
+            if !local_flags.contains(CLocalFlags::ECHOK | CLocalFlags::ECHOKE | CLocalFlags::ECHOE)
+            {
+                self.read_head = self.canon_head;
+                self.finish_erasing(ctx);
+                self.echo_char(ch, ctx);
+                // Add a newline if `ECHOK` is on and `ECHOKE` is off.
+                if local_flags.contains(CLocalFlags::ECHOK) {
+                    self.echo_raw(b'\n', ctx);
+                }
+                return;
+            }
+        }
+
+        let mut seen_alnums = 0;
+        while self.line_len() > 0 {
+            // Erase a single (possibly multi-byte) character.
+            let mut head = self.read_head;
+            let erased = loop {
+                head = head.wrapping_sub(1);
+                let erased = self.char_at(head);
+                if !is_continuation(erased, termios) || head == self.canon_head {
+                    break erased;
+                }
+            };
+            // Do not erase a partial character.
+            if is_continuation(erased, termios) {
+                break;
+            }
+
+            if kind == EraseKind::Word {
+                if erased.is_ascii_alphanumeric() || erased == b'_' {
+                    seen_alnums += 1;
+                } else if seen_alnums > 0 {
+                    break;
+                }
+            }
+
+            let num_bytes = self.read_head.wrapping_sub(head);
+            self.read_head = head;
+
+            if local_flags.contains(CLocalFlags::ECHO) {
+                if local_flags.contains(CLocalFlags::ECHOPRT) {
+                    if !self.is_erasing {
+                        self.echo_raw(b'\\', ctx);
+                        self.is_erasing = true;
+                    }
+                    self.echo_char(erased, ctx);
+                    for i in 1..num_bytes {
+                        self.echo_raw(self.char_at(head.wrapping_add(i)), ctx);
+                    }
+                } else if kind == EraseKind::Char && !local_flags.contains(CLocalFlags::ECHOE) {
+                    self.echo_char(ch, ctx);
+                } else if erased == b'\t' {
+                    self.erase_tab(ctx);
+                } else {
+                    let num_columns = if !is_cntrl(erased) {
+                        1
+                    } else if local_flags.contains(CLocalFlags::ECHOCTL) {
+                        2
+                    } else {
+                        0
+                    };
+                    for _ in 0..num_columns {
+                        self.echo_raw(b'\x08', ctx);
+                        self.echo_raw(b' ', ctx);
+                        self.echo_raw(b'\x08', ctx);
+                    }
+                }
+            }
+
+            if kind == EraseKind::Char {
+                break;
+            }
+        }
+
+        if self.line_len() == 0 && local_flags.contains(CLocalFlags::ECHO) {
+            self.finish_erasing(ctx);
+        }
+    }
+
+    /// Moves the cursor back to erase a tab that has just been removed from the current line.
+    fn erase_tab(&mut self, ctx: &mut ReceiveContext) {
+        let termios = ctx.termios();
+
+        // Count the columns used by the characters since the start of the line or the previous
+        // tab. They determine the width of the erased tab.
+        let mut num_columns = 0;
+        let mut is_after_tab = false;
+        let mut pos = self.read_head;
+        while pos != self.canon_head {
+            pos = pos.wrapping_sub(1);
+            let ch = self.char_at(pos);
+            if ch == b'\t' {
+                is_after_tab = true;
+                break;
+            } else if is_cntrl(ch) {
+                if termios.local_flags().contains(CLocalFlags::ECHOCTL) {
+                    num_columns += 2;
+                }
+            } else if !is_continuation(ch, termios) {
+                num_columns += 1;
+            }
+        }
+
+        if !is_after_tab {
+            num_columns += self.canon_column;
+        }
+
+        for _ in 0..(8 - num_columns % 8) {
+            self.echo_raw(b'\x08', ctx);
+        }
+    }
+
+    /// Finishes echoing the erased characters (with `ECHOPRT`).
+    fn finish_erasing(&mut self, ctx: &mut ReceiveContext) {
+        if self.is_erasing {
+            self.echo_raw(b'/', ctx);
+            self.is_erasing = false;
+        }
+    }
+
+    /// Echoes a character, where control characters are shown as `^X` if `ECHOCTL` is set.
+    fn echo_char(&mut self, ch: u8, ctx: &mut ReceiveContext) {
+        if ctx.termios().local_flags().contains(CLocalFlags::ECHOCTL) && is_cntrl(ch) && ch != b'\t'
+        {
+            ctx.echo(&[b'^', ch ^ 0x40]);
+            self.column += 2;
+            return;
+        }
+
+        self.echo_raw(ch, ctx);
+    }
+
+    /// Echoes a character with the output processing.
+    fn echo_raw(&mut self, ch: u8, ctx: &mut ReceiveContext) {
+        let termios = ctx.termios();
+        let output_flags = *termios.output_flags();
+
+        if !output_flags.contains(COutputFlags::OPOST) {
+            ctx.echo(&[ch]);
+            return;
+        }
+
+        let ch = match ch {
+            b'\n' => {
+                if output_flags.contains(COutputFlags::ONLRET) {
+                    self.column = 0;
+                }
+                if output_flags.contains(COutputFlags::ONLCR) {
+                    self.column = 0;
+                    self.canon_column = 0;
+                    ctx.echo(b"\r\n");
+                    return;
+                }
+                self.canon_column = self.column;
+                ch
+            }
+            b'\r' => {
+                if output_flags.contains(COutputFlags::ONOCR) && self.column == 0 {
+                    return;
+                }
+                if output_flags.contains(COutputFlags::OCRNL) {
+                    if output_flags.contains(COutputFlags::ONLRET) {
+                        self.column = 0;
+                        self.canon_column = 0;
+                    }
+                    b'\n'
+                } else {
+                    self.column = 0;
+                    self.canon_column = 0;
+                    ch
+                }
+            }
+            b'\t' => {
+                self.column += 8 - self.column % 8;
+                ch
+            }
+            b'\x08' => {
+                self.column = self.column.saturating_sub(1);
+                ch
+            }
+            ch if !is_cntrl(ch) => {
+                if !is_continuation(ch, termios) {
+                    self.column += 1;
+                }
+                if output_flags.contains(COutputFlags::OLCUC) {
+                    ch.to_ascii_uppercase()
+                } else {
+                    ch
+                }
+            }
+            ch => ch,
+        };
+
+        ctx.echo(&[ch]);
+    }
+
+    /// Reads a line in the canonical mode.
+    fn read_line(&mut self, dst: &mut [u8]) -> usize {
+        let len = dst.len().min(self.canon_head.wrapping_sub(self.read_tail));
+
+        let line_end =
+            (0..len).find(|i| self.line_ends[self.read_tail.wrapping_add(*i) % BUFFER_CAPACITY]);
+        let (len, read_len) = match line_end {
+            Some(i) if self.char_at(self.read_tail.wrapping_add(i)) == DISABLED_CHAR => {
+                // This allows the user space program to see the end of file.
+                (i + 1, i)
+            }
+            Some(i) => (i + 1, i + 1),
+            None => (len, len),
+        };
+
+        for (i, dst_i) in dst[..read_len].iter_mut().enumerate() {
+            *dst_i = self.char_at(self.read_tail.wrapping_add(i));
+        }
+
+        if let Some(i) = line_end {
+            self.line_ends
+                .set(self.read_tail.wrapping_add(i) % BUFFER_CAPACITY, false);
+        }
+        self.read_tail = self.read_tail.wrapping_add(len);
+
+        read_len
+    }
+
+    /// Reads characters in the non-canonical mode.
+    fn read_raw(&mut self, dst: &mut [u8]) -> usize {
+        let len = dst.len().min(self.buffer_len());
+
+        for (i, dst_i) in dst[..len].iter_mut().enumerate() {
+            *dst_i = self.char_at(self.read_tail.wrapping_add(i));
+        }
+        self.read_tail = self.read_tail.wrapping_add(len);
+
+        len
+    }
+}
+
+impl LineDiscipline for NTty {
+    fn id(&self) -> LdiscId {
+        LdiscId::Tty
+    }
+
+    fn receive_char(&mut self, ch: u8, ctx: &mut ReceiveContext) -> Result<()> {
+        let termios = ctx.termios();
+        let input_flags = *termios.input_flags();
+
+        let mut ch = ch;
+        if input_flags.contains(CInputFlags::ISTRIP) {
+            ch &= 0x7f;
+        }
+        if input_flags.contains(CInputFlags::IUCLC)
+            && termios.local_flags().contains(CLocalFlags::IEXTEN)
+        {
+            ch = ch.to_ascii_lowercase();
+        }
+
+        if self.is_lnext {
+            check_room(self.buffer_len())?;
+            self.is_lnext = false;
+            if input_flags.contains(CInputFlags::IXON | CInputFlags::IXANY) {
+                ctx.start_output();
+            }
+            self.receive_normal_char(ch, ctx);
+            return Ok(());
+        }
+
+        if input_flags.contains(CInputFlags::IXON) {
+            if is_char(ch, CCtrlCharId::VSTART, termios) {
+                ctx.start_output();
+                return Ok(());
+            }
+
+            if is_char(ch, CCtrlCharId::VSTOP, termios) {
+                ctx.stop_output();
+                return Ok(());
+            }
+        }
+
+        if let Some(signum) = char_to_signal(ch, termios) {
+            self.receive_signal_char(ch, signum, ctx);
+            return Ok(());
+        }
+
+        check_room(self.buffer_len())?;
+
+        if input_flags.contains(CInputFlags::IXON | CInputFlags::IXANY) {
+            ctx.start_output();
+        }
+
+        let (ch, is_translated) = match ch {
+            b'\r' if input_flags.contains(CInputFlags::IGNCR) => return Ok(()),
+            b'\r' if input_flags.contains(CInputFlags::ICRNL) => (b'\n', true),
+            b'\n' if input_flags.contains(CInputFlags::INLCR) => (b'\r', true),
+            ch => (ch, false),
+        };
+
+        if termios.is_canonical_mode() && self.receive_canon_char(ch, ctx) {
+            return Ok(());
+        }
+
+        if ch == b'\n' && is_translated {
+            // Unlike an ordinary newline character, which is echoed as `^J` if `ECHOCTL` is set, a
+            // newline character translated from a carriage return is always echoed as is.
+            if termios.local_flags().contains(CLocalFlags::ECHO) {
+                self.finish_erasing(ctx);
+                self.echo_raw(ch, ctx);
+            }
+            self.put_char(ch);
+            return Ok(());
+        }
+
+        self.receive_normal_char(ch, ctx);
+        Ok(())
+    }
+
+    fn try_read(&mut self, dst: &mut [u8], termios: &CTermios) -> Result<usize> {
+        if termios.is_canonical_mode() {
+            if self.canon_head == self.read_tail {
+                return_errno_with_message!(Errno::EAGAIN, "no complete lines are available");
+            }
+            Ok(self.read_line(dst))
+        } else {
+            if self.read_head == self.read_tail {
+                return_errno_with_message!(Errno::EAGAIN, "no characters are available");
+            }
+            Ok(self.read_raw(dst))
+        }
+    }
+
+    fn read_policy(&self, termios: &CTermios) -> ReadPolicy {
+        if termios.is_canonical_mode() {
+            return ReadPolicy {
+                min_len: 1,
+                timeout: None,
+                inter_byte_timeout: None,
+            };
+        }
+
+        let vmin = termios.special_char(CCtrlCharId::VMIN) as usize;
+        // `VTIME` is in units of 0.1 seconds.
+        let vtime = Duration::from_millis(termios.special_char(CCtrlCharId::VTIME) as u64 * 100);
+
+        if vmin > 0 {
+            // If `VTIME` is zero, wait until `VMIN` bytes are read. Otherwise, `VTIME` is the
+            // timeout between two bytes, and the timer starts after the first byte is read.
+            ReadPolicy {
+                min_len: vmin,
+                timeout: None,
+                inter_byte_timeout: (!vtime.is_zero()).then_some(vtime),
+            }
+        } else {
+            // If `VTIME` is zero, return immediately. Otherwise, `VTIME` is the timeout for the
+            // first byte.
+            ReadPolicy {
+                min_len: 1,
+                timeout: Some(vtime),
+                inter_byte_timeout: None,
+            }
+        }
+    }
+
+    fn is_readable(&self, termios: &CTermios) -> bool {
+        if termios.is_canonical_mode() {
+            return self.canon_head != self.read_tail;
+        }
+
+        // A read operation waiting for `VMIN` bytes without a timer will not return until the
+        // bytes are available, so the input is not considered available before that.
+        let vmin = termios.special_char(CCtrlCharId::VMIN) as usize;
+        let vtime = termios.special_char(CCtrlCharId::VTIME);
+        let min_len = if vtime == 0 && vmin > 0 { vmin } else { 1 };
+
+        self.buffer_len() >= min_len
+    }
+
+    fn readable_len(&self, termios: &CTermios) -> usize {
+        if !termios.is_canonical_mode() {
+            return self.buffer_len();
+        }
+
+        // The end-of-file characters are not counted because they will not be read.
+        let len = self.canon_head.wrapping_sub(self.read_tail);
+        let num_eofs = (0..len)
+            .map(|i| self.read_tail.wrapping_add(i))
+            .filter(|pos| {
+                self.line_ends[pos % BUFFER_CAPACITY] && self.char_at(*pos) == DISABLED_CHAR
+            })
+            .count();
+        len - num_eofs
+    }
+
+    fn buffer_len(&self) -> usize {
+        self.read_head.wrapping_sub(self.read_tail)
+    }
+
+    fn buffer_capacity(&self) -> usize {
+        BUFFER_CAPACITY
+    }
+
+    fn flush_input(&mut self) {
+        self.read_tail = 0;
+        self.canon_head = 0;
+        self.read_head = 0;
+        self.line_ends.fill(false);
+        self.is_lnext = false;
+        self.is_erasing = false;
+    }
+
+    fn on_termios_change(&mut self, old_termios: &CTermios, new_termios: &CTermios) {
+        if old_termios.is_canonical_mode() == new_termios.is_canonical_mode() {
+            return;
+        }
+
+        self.line_ends.fill(false);
+        if !new_termios.is_canonical_mode() || self.buffer_len() == 0 {
+            self.canon_head = self.read_tail;
+        } else {
+            // When switching to the canonical mode, the pending characters become a complete line,
+            // so they can be read immediately.
+            self.line_ends
+                .set(self.read_head.wrapping_sub(1) % BUFFER_CAPACITY, true);
+            self.canon_head = self.read_head;
+        }
+        self.is_lnext = false;
+        self.is_erasing = false;
+    }
+}
+
+impl EraseKind {
+    fn from_char(ch: u8, termios: &CTermios) -> Option<Self> {
+        if is_char(ch, CCtrlCharId::VERASE, termios) {
+            Some(Self::Char)
+        } else if is_char(ch, CCtrlCharId::VKILL, termios) {
+            Some(Self::Line)
+        } else if is_char(ch, CCtrlCharId::VWERASE, termios)
+            && termios.local_flags().contains(CLocalFlags::IEXTEN)
+        {
+            Some(Self::Word)
+        } else {
+            None
+        }
+    }
+}
+
+/// Fails with [`Errno::EAGAIN`] if the buffer has no room for a new character.
+fn check_room(buffer_len: usize) -> Result<()> {
+    if buffer_len >= BUFFER_CAPACITY {
+        // If the buffer is full, we should not push the character into the buffer. The caller
+        // can silently ignore the error (if the input comes from the keyboard) or block the
+        // user space (if the input comes from the pseduoterminal master).
+        return_errno_with_message!(Errno::EAGAIN, "the buffer is full");
+    }
+
+    Ok(())
+}
+
+/// Returns whether the character is the (enabled) control character.
+fn is_char(ch: u8, id: CCtrlCharId, termios: &CTermios) -> bool {
+    termios.enabled_special_char(id) == Some(ch)
+}
+
+fn is_cntrl(ch: u8) -> bool {
+    ch < 0x20 || ch == 0x7f
+}
+
+/// Returns whether the character is a UTF-8 continuation byte.
+fn is_continuation(ch: u8, termios: &CTermios) -> bool {
+    termios.input_flags().contains(CInputFlags::IUTF8) && (ch & 0xc0) == 0x80
+}
+
+fn char_to_signal(ch: u8, termios: &CTermios) -> Option<SigNum> {
+    if !termios.local_flags().contains(CLocalFlags::ISIG) {
+        return None;
+    }
+
+    match ch {
+        ch if is_char(ch, CCtrlCharId::VINTR, termios) => Some(SIGINT),
+        ch if is_char(ch, CCtrlCharId::VQUIT, termios) => Some(SIGQUIT),
+        ch if is_char(ch, CCtrlCharId::VSUSP, termios) => Some(SIGTSTP),
+        _ => None,
+    }
+}
@@ -3,7 +3,10 @@
 use device_id::{DeviceId, MajorId, MinorId};
 use ostd::sync::LocalIrqDisabled;
 
-use self::{line_discipline::LineDiscipline, termio::CFontOp};
+use self::{
+    line_discipline::{LdiscId, TtyLdisc},
+    termio::{CCtrlCharId, CFontOp, CLocalFlags},
+};
 use crate::{
     device::{Device, DeviceType},
     events::IoEvents,
@@ -11,7 +14,11 @@ use crate::{
     prelude::*,
     process::{
         JobControl, Terminal, broadcast_signal_async,
-        signal::{PollHandle, Pollable, Pollee},
+        signal::{
+            PollHandle, Pollable, Pollee,
+            constants::{SIGTTIN, SIGTTOU, SIGWINCH},
+            signals::kernel::KernelSignal,
+        },
     },
     util::ioctl::{RawIoctl, dispatch_ioctl},
 };
@@ -65,7 +72,7 @@ const IO_CAPACITY: usize = 4096;
 pub struct Tty<D> {
     index: u32,
     driver: D,
-    ldisc: SpinLock<LineDiscipline, LocalIrqDisabled>,
+    ldisc: SpinLock<TtyLdisc, LocalIrqDisabled>,
     job_control: JobControl,
     pollee: Pollee,
     tty_flags: TtyFlags,
@@ -74,10 +81,12 @@ pub struct Tty<D> {
 
 impl<D> Tty<D> {
     pub(super) fn new(index: u32, driver: D) -> Arc<Self> {
+        let termios = driver.init_termios();
+
         Arc::new_cyclic(move |weak_ref| Tty {
             index,
             driver,
-            ldisc: SpinLock::new(LineDiscipline::new()),
+            ldisc: SpinLock::new(TtyLdisc::new(termios)),
             job_control: JobControl::new(),
             pollee: Pollee::new(),
             tty_flags: TtyFlags::new(),
@@ -138,6 +147,7 @@ impl<D: TtyDriver> Tty<D> {
                     }
                 },
                 &mut echo,
+                |is_stopped| self.on_flow_change(is_stopped),
             );
             if res.is_err() && len == 0 {
                 return_errno_with_message!(Errno::EAGAIN, "the line discipline is full");
@@ -147,19 +157,53 @@ impl<D: TtyDriver> Tty<D> {
                 len += 1;
             }
         }
+        drop(echo);
+
+        self.check_throttle(&mut ldisc);
 
         self.pollee.notify(IoEvents::IN | IoEvents::RDNORM);
         Ok(len)
     }
 
+    /// Notifies the driver if the input should be throttled or unthrottled.
+    fn check_throttle(&self, ldisc: &mut TtyLdisc) {
+        if let Some(is_throttled) = ldisc.check_throttle() {
+            self.driver
+                .on_throttle_change(is_throttled, ldisc.termios());
+        }
+    }
+
+    /// Notifies that the output is stopped or restarted by the flow control.
+    fn on_flow_change(&self, is_stopped: bool) {
+        self.driver.on_flow_change(is_stopped);
+
+        if !is_stopped {
+            self.pollee.notify(IoEvents::OUT);
+        }
+    }
+
+    /// Pushes characters into the output buffer if the output is not stopped.
+    fn try_push_output(&self, chs: &[u8]) -> Result<usize> {
+        if self.ldisc.lock().is_output_stopped() {
+            return_errno_with_message!(Errno::EAGAIN, "the output is stopped");
+        }
+
+        self.driver.push_output(chs)
+    }
+
     fn check_io_events(&self) -> IoEvents {
         let mut events = IoEvents::empty();
 
-        if self.ldisc.lock().buffer_len() > 0 {
+        let (is_readable, is_output_stopped) = {
+            let ldisc = self.ldisc.lock();
+            (ldisc.is_readable(), ldisc.is_output_stopped())
+        };
+
+        if is_readable {
             events |= IoEvents::IN | IoEvents::RDNORM;
         }
 
-        if self.driver.can_push() {
+        if self.driver.can_push() && !is_output_stopped {
             events |= IoEvents::OUT;
         }
 
@@ -184,39 +228,85 @@ impl<D: TtyDriver> Tty<D> {
             return Ok(0);
         }
 
-        self.job_control.wait_until_in_foreground()?;
+        self.job_control.check_background_access(SIGTTIN)?;
 
-        // TODO: Add support for timeout.
         let mut buf = vec![0u8; writer.avail().min(IO_CAPACITY)];
         let is_nonblocking = status_flags.contains(StatusFlags::O_NONBLOCK);
-        let read_len = if is_nonblocking {
-            self.ldisc.lock().try_read(&mut buf)?
-        } else {
-            self.wait_events(IoEvents::IN, None, || self.ldisc.lock().try_read(&mut buf))?
-        };
-        self.pollee.invalidate();
-        self.driver.notify_input();
+        let read_len = self.read_input(&mut buf, is_nonblocking)?;
 
         // TODO: Confirm what we should do if `write_fallible` fails in the middle.
         writer.write_fallible(&mut buf[..read_len].into())?;
         Ok(read_len)
     }
 
+    /// Reads the input into `buf`, waiting for the input as the line discipline requires.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/tty/n_tty.c>.
+    fn read_input(&self, buf: &mut [u8], is_nonblocking: bool) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let policy = self.ldisc.lock().read_policy();
+        let mut timeout = policy.timeout;
+        let mut read_len = 0;
+
+        loop {
+            let is_timeout_zero = timeout.is_some_and(|duration| duration.is_zero());
+
+            let mut try_read = || self.ldisc.lock().try_read(&mut buf[read_len..]);
+            let res = if is_nonblocking || is_timeout_zero {
+                try_read()
+            } else {
+                self.wait_events(IoEvents::IN, timeout.as_ref(), try_read)
+            };
+
+            match res {
+                Ok(len) => {
+                    read_len += len;
+
+                    self.pollee.invalidate();
+                    self.check_throttle(&mut self.ldisc.lock());
+                    self.driver.notify_input();
+
+                    // Reading zero bytes means the end of file.
+                    if len == 0 || read_len >= policy.min_len || read_len == buf.len() {
+                        return Ok(read_len);
+                    }
+                    if let Some(inter_byte_timeout) = policy.inter_byte_timeout {
+                        timeout = Some(inter_byte_timeout);
+                    }
+                }
+                Err(err) if err.error() == Errno::ETIME => return Ok(read_len),
+                Err(err) if err.error() == Errno::EAGAIN && is_timeout_zero => return Ok(read_len),
+                // If some bytes have been read, they are returned even if an error (e.g., `EAGAIN`
+                // or `EINTR`) occurs later.
+                Err(_) if read_len > 0 => return Ok(read_len),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
     pub fn write(&self, reader: &mut VmReader, status_flags: StatusFlags) -> Result<usize> {
         if self.tty_flags.is_other_closed() {
             return_errno_with_message!(Errno::EIO, "the TTY is closed");
         }
 
+        let local_flags = *self.ldisc.lock().termios().local_flags();
+        if local_flags.contains(CLocalFlags::TOSTOP) {
+            self.job_control.check_background_access(SIGTTOU)?;
+        }
+
         let mut buf = vec![0u8; reader.remain().min(IO_CAPACITY)];
         let write_len = reader.read_fallible(&mut buf.as_mut_slice().into())?;
 
         // TODO: Add support for timeout.
         let is_nonblocking = status_flags.contains(StatusFlags::O_NONBLOCK);
         let len = if is_nonblocking {
-            self.driver.push_output(&buf[..write_len])?
+            self.try_push_output(&buf[..write_len])?
         } else {
             self.wait_events(IoEvents::OUT, None, || {
-                self.driver.push_output(&buf[..write_len])
+                self.try_push_output(&buf[..write_len])
             })?
         };
         self.pollee.invalidate();
@@ -235,6 +325,7 @@ impl<D: TtyDriver> Tty<D> {
                 cmd.write(&termios)?;
             }
             cmd @ SetTermios => {
+                self.job_control.check_background_access(SIGTTOU)?;
                 let termios = cmd.read()?;
 
                 let mut ldisc = self.ldisc.lock();
@@ -243,6 +334,7 @@ impl<D: TtyDriver> Tty<D> {
                 ldisc.set_termios(termios);
             }
             cmd @ SetTermiosWait => {
+                self.job_control.check_background_access(SIGTTOU)?;
                 let termios = cmd.read()?;
 
                 // TODO: If applicable, wait for the output buffer to drain. For now, we don't need
@@ -257,6 +349,7 @@ impl<D: TtyDriver> Tty<D> {
                 ldisc.set_termios(termios);
             }
             cmd @ SetTermiosFlush => {
+                self.job_control.check_background_access(SIGTTOU)?;
                 let termios = cmd.read()?;
 
                 // TODO: If applicable, wait for the output buffer to drain. (See comments above.)
@@ -264,10 +357,72 @@ impl<D: TtyDriver> Tty<D> {
                 let old_termios = ldisc.termios();
                 self.driver().on_termios_change(old_termios, &termios);
                 ldisc.set_termios(termios);
-                ldisc.drain_input();
+                ldisc.flush_input();
+                self.driver.on_input_flush();
+                self.check_throttle(&mut ldisc);
 
                 self.pollee.invalidate();
             }
+            _cmd @ SendBreak => {
+                self.job_control.check_background_access(SIGTTOU)?;
+
+                // TODO: Send a break if the argument is zero. Otherwise, this is used by
+                // `tcdrain()` to wait for the output buffer to drain. We don't need to do anything
+                // here for the same reason as `TCSETSW` (see comments above).
+            }
+            cmd @ FlowControl => {
+                self.job_control.check_background_access(SIGTTOU)?;
+
+                // Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/tty/tty_ioctl.c>.
+                match cmd.get() {
+                    TCOOFF => {
+                        if self.ldisc.lock().stop_output_by_ioctl() {
+                            self.on_flow_change(true);
+                        }
+                    }
+                    TCOON => {
+                        if self.ldisc.lock().start_output_by_ioctl() {
+                            self.on_flow_change(false);
+                        }
+                    }
+                    TCIOFF | TCION => {
+                        let char_id = if cmd.get() == TCIOFF {
+                            CCtrlCharId::VSTOP
+                        } else {
+                            CCtrlCharId::VSTART
+                        };
+                        let ch = self.ldisc.lock().termios().enabled_special_char(char_id);
+                        if let Some(ch) = ch {
+                            self.driver.send_xchar(ch);
+                        }
+                    }
+                    _ => return_errno_with_message!(Errno::EINVAL, "the action is invalid"),
+                }
+            }
+            cmd @ FlushQueue => {
+                self.job_control.check_background_access(SIGTTOU)?;
+
+                let (flush_input, flush_output) = match cmd.get() {
+                    TCIFLUSH => (true, false),
+                    TCOFLUSH => (false, true),
+                    TCIOFLUSH => (true, true),
+                    _ => return_errno_with_message!(Errno::EINVAL, "the queue is invalid"),
+                };
+
+                if flush_input {
+                    let mut ldisc = self.ldisc.lock();
+                    ldisc.flush_input();
+                    self.driver.on_input_flush();
+                    self.check_throttle(&mut ldisc);
+                    drop(ldisc);
+
+                    self.pollee.invalidate();
+                    self.driver.notify_input();
+                }
+                if flush_output {
+                    self.driver.flush_output();
+                }
+            }
             cmd @ GetWinSize => {
                 let winsize = self.ldisc.lock().window_size();
 
@@ -276,16 +431,40 @@ impl<D: TtyDriver> Tty<D> {
             cmd @ SetWinSize => {
                 let winsize = cmd.read()?;
 
-                self.ldisc.lock().set_window_size(winsize);
+                if !self.ldisc.lock().set_window_size(winsize) {
+                    return Ok(0);
+                }
+
+                // Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/tty/tty_io.c>.
+                if let Some(foreground) = self.job_control.foreground() {
+                    foreground.broadcast_signal(KernelSignal::new(SIGWINCH));
+                }
+            }
+            cmd @ SetLdisc => {
+                self.job_control.check_background_access(SIGTTOU)?;
+                let id = LdiscId::try_from(cmd.read()?)?;
+
+                let mut ldisc = self.ldisc.lock();
+                ldisc.set_ldisc(id)?;
+                self.check_throttle(&mut ldisc);
+                drop(ldisc);
+
+                self.pollee.invalidate();
+                self.driver.notify_input();
+            }
+            cmd @ GetLdisc => {
+                let id = self.ldisc.lock().id();
+
+                cmd.write(&(id as i32))?;
             }
             cmd @ GetNumBytesToRead => {
                 if self.tty_flags.is_other_closed() {
                     return_errno_with_message!(Errno::EIO, "the TTY is closed");
                 }
 
-                let buffer_len = self.ldisc.lock().buffer_len() as i32;
+                let readable_len = self.ldisc.lock().readable_len() as i32;
 
-                cmd.write(&buffer_len)?;
+                cmd.write(&readable_len)?;
             }
 
             _ => {
@@ -331,6 +510,10 @@ impl<D: TtyDriver> Device for Tty<D> {
         self.driver.devtmpfs_path(self.index)
     }
 
+    fn class(&self) -> &'static str {
+        "tty"
+    }
+
     fn open(&self) -> Result<Box<dyn FileIo>> {
         D::open(self.weak_self.upgrade().unwrap())
     }
@@ -2,7 +2,7 @@
 
 use alloc::{boxed::Box, format, sync::Arc};
 
-use aster_console::AnyConsoleDevice;
+use aster_uart::{AnyUartDevice, LineConfig, ModemLines, Parity};
 use ostd::mm::{Infallible, VmReader, VmWriter};
 use spin::Once;
 
@@ -10,16 +10,22 @@ use super::{Tty, TtyDriver};
 use crate::{
     device::{
         registry::char,
-        tty::{file::TtyFile, termio::CTermios},
+        tty::{
+            device::console_options,
+            file::TtyFile,
+            termio::{CCtrlBaud, CCtrlCharId, CCtrlFlags, CCtrlSize, CInputFlags, CTermios},
+        },
     },
     fs::file::FileIo,
     prelude::*,
+    util::ioctl::{RawIoctl, dispatch_ioctl},
 };
 
 /// The driver for serial devices.
 #[derive(Clone)]
 pub(super) struct SerialDriver {
-    console: Arc<dyn AnyConsoleDevice>,
+    port: Arc<dyn AnyUartDevice>,
+    init_termios: CTermios,
 }
 
 impl SerialDriver {
@@ -39,12 +45,12 @@ impl TtyDriver for SerialDriver {
     }
 
     fn push_output(&self, chs: &[u8]) -> Result<usize> {
-        self.console.send(chs);
+        self.port.send(chs);
         Ok(chs.len())
     }
 
     fn echo_callback(&self) -> impl FnMut(&[u8]) + '_ {
-        |chs| self.console.send(chs)
+        |chs| self.port.send(chs)
     }
 
     fn can_push(&self) -> bool {
@@ -53,45 +59,201 @@ impl TtyDriver for SerialDriver {
 
     fn notify_input(&self) {}
 
-    fn on_termios_change(&self, _old_termios: &CTermios, _new_termios: &CTermios) {}
+    fn init_termios(&self) -> CTermios {
+        self.init_termios
+    }
+
+    fn on_termios_change(&self, old_termios: &CTermios, new_termios: &CTermios) {
+        if old_termios.ctrl_flags() == new_termios.ctrl_flags() {
+            return;
+        }
+
+        configure_port(
+            self.port.as_ref(),
+            new_termios.ctrl_flags(),
+            Some(old_termios.ctrl_flags()),
+        );
+    }
+
+    fn on_throttle_change(&self, is_throttled: bool, termios: &CTermios) {
+        if !termios.input_flags().contains(CInputFlags::IXOFF) {
+            return;
+        }
+
+        let char_id = if is_throttled {
+            CCtrlCharId::VSTOP
+        } else {
+            CCtrlCharId::VSTART
+        };
+        self.port.send(&[termios.special_char(char_id)]);
+    }
+
+    fn ioctl(&self, _tty: &Tty<Self>, raw_ioctl: RawIoctl) -> Result<bool>
+    where
+        Self: Sized,
+    {
+        use super::ioctl_defs::*;
+
+        dispatch_ioctl!(match raw_ioctl {
+            cmd @ GetModemLines => {
+                cmd.write(&self.port.modem_lines().bits())?;
+            }
+            cmd @ SetModemBits => {
+                let lines = ModemLines::from_bits_truncate(cmd.read()?);
+                self.port.set_modem_lines(self.port.modem_lines() | lines);
+            }
+            cmd @ ClearModemBits => {
+                let lines = ModemLines::from_bits_truncate(cmd.read()?);
+                self.port.set_modem_lines(self.port.modem_lines() - lines);
+            }
+            cmd @ SetModemLines => {
+                let lines = ModemLines::from_bits_truncate(cmd.read()?);
+                self.port.set_modem_lines(lines);
+            }
+            _ => return Ok(false),
+        });
+
+        Ok(true)
+    }
+}
+
+/// Configures the serial line according to the control flags of the termios.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/tty/serial/serial_core.c>.
+fn configure_port(
+    port: &dyn AnyUartDevice,
+    ctrl_flags: &CCtrlFlags,
+    old_ctrl_flags: Option<&CCtrlFlags>,
+) {
+    let Ok(baud) = ctrl_flags.baud() else {
+        // TODO: Support arbitrary baud rates specified by `BOTHER`.
+        return;
+    };
+    let was_hung_up = old_ctrl_flags.is_some_and(|flags| flags.baud().is_ok_and(is_hang_up));
+
+    // Setting the baud rate to zero hangs up the line by dropping DTR and RTS.
+    if is_hang_up(baud) {
+        port.set_modem_lines(port.modem_lines() - (ModemLines::DTR | ModemLines::RTS));
+        return;
+    }
+
+    let data_bits = match ctrl_flags.size() {
+        Ok(CCtrlSize::CS5) => 5,
+        Ok(CCtrlSize::CS6) => 6,
+        Ok(CCtrlSize::CS7) => 7,
+        Ok(CCtrlSize::CS8) | Err(_) => 8,
+    };
+    let parity = if !ctrl_flags.contains(CCtrlFlags::PARENB) {
+        Parity::None
+    } else if ctrl_flags.contains(CCtrlFlags::PARODD) {
+        Parity::Odd
+    } else {
+        Parity::Even
+    };
+    let config = LineConfig {
+        baud_rate: baud.rate(),
+        data_bits,
+        parity,
+        two_stop_bits: ctrl_flags.contains(CCtrlFlags::CSTOPB),
+    };
+    port.set_line_config(&config);
+
+    // Raise DTR and RTS again if the line was hung up.
+    if was_hung_up {
+        port.set_modem_lines(port.modem_lines() | ModemLines::DTR | ModemLines::RTS);
+    }
 }
 
-static SERIAL0: Once<Arc<Tty<SerialDriver>>> = Once::new();
+fn is_hang_up(baud: CCtrlBaud) -> bool {
+    matches!(baud, CCtrlBaud::B0)
+}
 
-/// Returns the `ttyS0` device.
+/// Returns the initial termios of a serial device.
+///
+/// If the serial device is used as a console, the options of the console (e.g., "115200n8" in
+/// "console=ttyS0,115200n8") will be applied to the termios.
+///
+/// Reference: <https://www.kernel.org/doc/html/latest/admin-guide/serial-console.html>.
+fn init_termios(name: &str) -> CTermios {
+    let mut termios = CTermios::default();
+
+    // The baud rate defaults to 115200 bps, which is the baud rate that the UART is initialized
+    // with during the boot process.
+    let ctrl_flags = termios.ctrl_flags_mut();
+    ctrl_flags.set_baud(CCtrlBaud::B115200);
+    ctrl_flags.set(CCtrlFlags::HUPCL | CCtrlFlags::CLOCAL, true);
+
+    let Some(options) = console_options(name) else {
+        return termios;
+    };
+
+    let baud_len = options
+        .find(|ch: char| !ch.is_ascii_digit())
+        .unwrap_or(options.len());
+    let (baud_rate, options) = options.split_at(baud_len);
+    match baud_rate.parse().ok().and_then(CCtrlBaud::from_rate) {
+        Some(baud) => ctrl_flags.set_baud(baud),
+        None => warn!(
+            "invalid baud rate for the '{}' console: {}",
+            name, baud_rate
+        ),
+    }
+
+    let mut options = options.bytes();
+    match options.next() {
+        Some(b'o') => ctrl_flags.set(CCtrlFlags::PARENB | CCtrlFlags::PARODD, true),
+        Some(b'e') => ctrl_flags.set(CCtrlFlags::PARENB, true),
+        _ => (),
+    }
+    match options.next() {
+        Some(b'5') => ctrl_flags.set_size(CCtrlSize::CS5),
+        Some(b'6') => ctrl_flags.set_size(CCtrlSize::CS6),
+        Some(b'7') => ctrl_flags.set_size(CCtrlSize::CS7),
+        _ => (),
+    }
+    // TODO: Support the hardware flow control specified by 'r'.
+
+    termios
+}
+
+static SERIALS: Once<Vec<Arc<Tty<SerialDriver>>>> = Once::new();
+
+/// Returns the `ttyS<port_num>` device.
 ///
 /// Returns `None` if the device is not found nor initialized.
-pub(super) fn serial0_device() -> Option<&'static Arc<Tty<SerialDriver>>> {
-    SERIAL0.get()
+pub(super) fn serial_device(port_num: u32) -> Option<&'static Arc<Tty<SerialDriver>>> {
+    SERIALS
+        .get()?
+        .iter()
+        .find(|serial| serial.index() == SerialDriver::MINOR_ID_BASE + port_num)
 }
 
 pub(super) fn init_in_first_process() -> Result<()> {
-    let devices = aster_console::all_devices();
-
-    // Initialize the `ttyS0` device if the serial console is available.
+    let mut serials = Vec::new();
 
-    let serial_console = devices
-        .iter()
-        .find(|(name, _)| name.as_str() == aster_uart::CONSOLE_NAME)
-        .map(|(_, device)| device.clone());
+    for (port_num, port) in aster_uart::all_devices() {
+        let init_termios = init_termios(&format!("ttyS{}", port_num));
+        configure_port(port.as_ref(), init_termios.ctrl_flags(), None);
 
-    if let Some(serial_console) = serial_console {
         let driver = SerialDriver {
-            console: serial_console.clone(),
+            port: port.clone(),
+            init_termios,
         };
-        let serial0 = Tty::new(SerialDriver::MINOR_ID_BASE, driver);
+        let serial = Tty::new(SerialDriver::MINOR_ID_BASE + port_num, driver);
 
-        SERIAL0.call_once(|| serial0.clone());
-        char::register(serial0.clone())?;
+        char::register(serial.clone())?;
+        serials.push(serial.clone());
 
-        serial_console.register_callback(Box::leak(Box::new(
+        port.register_callback(Box::leak(Box::new(
             move |mut reader: VmReader<Infallible>| {
                 let mut chs = vec![0u8; reader.remain()];
                 reader.read(&mut VmWriter::from(chs.as_mut_slice()));
-                let _ = serial0.push_input(chs.as_slice());
+                let _ = serial.push_input(chs.as_slice());
             },
         )));
     }
 
+    SERIALS.call_once(|| serials);
+
     Ok(())
 }
@@ -7,6 +7,11 @@ use crate::prelude::*;
 /// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/asm-generic/termbits-common.h#L5>.
 type CCtrlChar = u8;
 
+/// The value that disables a control character; `__DISABLED_CHAR` in Linux.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/linux/tty.h>.
+pub(super) const DISABLED_CHAR: CCtrlChar = b'\0';
+
 bitflags! {
     /// The input flags; `c_iflags` bits in Linux.
     #[derive(Pod)]
@@ -63,7 +68,7 @@ impl Default for COutputFlags {
 
 /// The control flags; `c_cflags` bits in Linux.
 #[repr(C)]
-#[derive(Debug, Clone, Copy, Pod)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Pod)]
 pub(super) struct CCtrlFlags(u32);
 
 impl Default for CCtrlFlags {
@@ -80,27 +85,59 @@ impl CCtrlFlags {
     const SIZE_MASK: u32 = 0x00000030;
     const READ_BIT: u32 = 0x00000080;
 
-    #[expect(dead_code)]
+    // https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/asm-generic/termbits.h
+    /// Uses two stop bits instead of one.
+    pub(super) const CSTOPB: u32 = 0x00000040;
+    /// Enables the parity generation and checking.
+    pub(super) const PARENB: u32 = 0x00000100;
+    /// Uses the odd parity instead of the even parity.
+    pub(super) const PARODD: u32 = 0x00000200;
+    /// Lowers the modem control lines after the last process closes the device.
+    pub(super) const HUPCL: u32 = 0x00000400;
+    /// Ignores the modem control lines.
+    pub(super) const CLOCAL: u32 = 0x00000800;
+
     pub(super) fn baud(&self) -> Result<CCtrlBaud> {
         let baud = self.0 & Self::BAUD_MASK;
         Ok(CCtrlBaud::try_from(baud)?)
     }
 
-    #[expect(dead_code)]
+    pub(super) fn set_baud(&mut self, baud: CCtrlBaud) {
+        self.0 = (self.0 & !Self::BAUD_MASK) | baud as u32;
+    }
+
     pub(super) fn size(&self) -> Result<CCtrlSize> {
         let size = self.0 & Self::SIZE_MASK;
         Ok(CCtrlSize::try_from(size)?)
     }
 
+    pub(super) fn set_size(&mut self, size: CCtrlSize) {
+        self.0 = (self.0 & !Self::SIZE_MASK) | size as u32;
+    }
+
     #[expect(dead_code)]
     pub(super) fn is_read(&self) -> bool {
         self.0 & Self::READ_BIT != 0
     }
+
+    /// Returns whether all the bits in `flags` (e.g., [`Self::CSTOPB`]) are set.
+    pub(super) fn contains(&self, flags: u32) -> bool {
+        self.0 & flags == flags
+    }
+
+    /// Sets or clears the bits in `flags` (e.g., [`Self::CSTOPB`]).
+    pub(super) fn set(&mut self, flags: u32, value: bool) {
+        if value {
+            self.0 |= flags;
+        } else {
+            self.0 &= !flags;
+        }
+    }
 }
 
 /// The size part of the control flags ([`CCtrlFlags`]).
 #[repr(u32)]
-#[derive(Clone, Copy, TryFromInt)]
+#[derive(Debug, Clone, Copy, TryFromInt)]
 pub(super) enum CCtrlSize {
     // https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/asm-generic/termbits.h#L97
     CS5 = 0x00000000,
@@ -130,6 +167,69 @@ pub(super) enum CCtrlBaud {
     B9600 = 0x0000000d,
     B19200 = 0x0000000e,
     B38400 = 0x0000000f,
+    // https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/asm-generic/termbits.h
+    B57600 = 0x00001001,
+    B115200 = 0x00001002,
+    B230400 = 0x00001003,
+    B460800 = 0x00001004,
+    B500000 = 0x00001005,
+    B576000 = 0x00001006,
+    B921600 = 0x00001007,
+    B1000000 = 0x00001008,
+    B1152000 = 0x00001009,
+    B1500000 = 0x0000100a,
+    B2000000 = 0x0000100b,
+    B2500000 = 0x0000100c,
+    B3000000 = 0x0000100d,
+    B3500000 = 0x0000100e,
+    B4000000 = 0x0000100f,
+}
+
+impl CCtrlBaud {
+    /// Returns the baud rate in bits per second.
+    pub(super) fn rate(self) -> u32 {
+        match self {
+            Self::B0 => 0,
+            Self::B50 => 50,
+            Self::B75 => 75,
+            Self::B110 => 110,
+            Self::B134 => 134,
+            Self::B150 => 150,
+            Self::B200 => 200,
+            Self::B300 => 300,
+            Self::B600 => 600,
+            Self::B1200 => 1200,
+            Self::B1800 => 1800,
+            Self::B2400 => 2400,
+            Self::B4800 => 4800,
+            Self::B9600 => 9600,
+            Self::B19200 => 19200,
+            Self::B38400 => 38400,
+            Self::B57600 => 57600,
+            Self::B115200 => 115200,
+            Self::B230400 => 230400,
+            Self::B460800 => 460800,
+            Self::B500000 => 500000,
+            Self::B576000 => 576000,
+            Self::B921600 => 921600,
+            Self::B1000000 => 1000000,
+            Self::B1152000 => 1152000,
+            Self::B1500000 => 1500000,
+            Self::B2000000 => 2000000,
+            Self::B2500000 => 2500000,
+            Self::B3000000 => 3000000,
+            Self::B3500000 => 3500000,
+            Self::B4000000 => 4000000,
+        }
+    }
+
+    /// Returns the baud that has exactly the baud rate, or `None` if there is no such baud.
+    pub(super) fn from_rate(rate: u32) -> Option<Self> {
+        (0x0000u32..=0x000f)
+            .chain(0x1001..=0x100f)
+            .filter_map(|value| Self::try_from(value).ok())
+            .find(|baud| baud.rate() == rate)
+    }
 }
 
 bitflags! {
@@ -284,6 +384,22 @@ impl CTermios {
         &mut self.c_cc[id as usize]
     }
 
+    /// Returns the control character, or `None` if the control character is disabled.
+    pub(super) fn enabled_special_char(&self, id: CCtrlCharId) -> Option<CCtrlChar> {
+        let ch = self.special_char(id);
+        (ch != DISABLED_CHAR).then_some(ch)
+    }
+
+    /// Returns the line discipline number.
+    pub(super) fn line(&self) -> CCtrlChar {
+        self.c_line
+    }
+
+    /// Sets the line discipline number.
+    pub(super) fn set_line(&mut self, line: CCtrlChar) {
+        self.c_line = line;
+    }
+
     /// Returns whether the terminal is in the canonical mode.
     ///
     /// The canonical mode means that the input characters will be handled by lines, not by single
@@ -297,6 +413,21 @@ impl CTermios {
         &self.c_iflags
     }
 
+    /// Returns the output flags.
+    pub(super) fn output_flags(&self) -> &COutputFlags {
+        &self.c_oflags
+    }
+
+    /// Returns the control flags.
+    pub(super) fn ctrl_flags(&self) -> &CCtrlFlags {
+        &self.c_cflags
+    }
+
+    /// Returns a mutable reference to the control flags.
+    pub(super) fn ctrl_flags_mut(&mut self) -> &mut CCtrlFlags {
+        &mut self.c_cflags
+    }
+
     /// Returns the local flags.
     pub fn local_flags(&self) -> &CLocalFlags {
         &self.c_lflags
@@ -306,7 +437,7 @@ impl CTermios {
 /// A window size; `struct winsize` in Linux.
 ///
 /// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/asm-generic/termios.h#L15>.
-#[derive(Debug, Clone, Copy, Default, Pod)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Pod)]
 #[repr(C)]
 pub struct CWinSize {
     ws_row: u16,
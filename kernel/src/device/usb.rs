@@ -0,0 +1,18 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The USB devices.
+//!
+//! The devices are enumerated by a kernel thread, which handles the events of the hubs. The
+//! drivers of the USB interfaces are registered by the subsystems that use them.
+
+use crate::{prelude::*, thread::kernel_thread::ThreadOptions};
+
+pub(super) fn init_in_first_kthread() {
+    let task_fn = || {
+        info!("spawn the USB hub thread");
+        loop {
+            aster_usb::handle_hub_events();
+        }
+    };
+    ThreadOptions::new(task_fn).spawn();
+}
@@ -0,0 +1,366 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Virtio console ports (`/dev/vport<D>p<N>`).
+//!
+//! The generic ports of virtio console devices are channels between the host and the guest,
+//! which are usually used by guest agents. A port appears as `/dev/vport<D>p<N>`, where `<D>` is
+//! the index of the virtio console device and `<N>` is the ID of the port. If the port has a name,
+//! it can also be found at `/dev/virtio-ports/<name>`.
+//!
+//! The console ports appear as `/dev/vport<D>p<N>` too, but they can only be opened as `hvc<N>`
+//! devices.
+//!
+//! Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/char/virtio_console.c>.
+
+use alloc::format;
+
+use aster_virtio::device::console::{self, ConsolePort};
+use device_id::{DeviceId, MinorId};
+use ostd::{
+    mm::{Infallible, VmReader, VmWriter},
+    sync::LocalIrqDisabled,
+};
+use spin::Once;
+
+use super::{
+    Device, DeviceType,
+    registry::char::{self, MajorIdOwner},
+};
+use crate::{
+    events::IoEvents,
+    fs::{
+        devtmpfs,
+        file::{FileIo, StatusFlags},
+        vfs::inode::InodeIo,
+    },
+    prelude::*,
+    process::signal::{PollHandle, Pollable, Pollee},
+    thread::work_queue::{WorkPriority, submit_work_item, work_item::WorkItem},
+    util::ring_buffer::RingBuffer,
+};
+
+/// The capacity of the buffer that holds the data received from the host.
+const BUFFER_CAPACITY: usize = 64 * 1024;
+
+/// The maximum number of bytes that are sent to the host at a time.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/char/virtio_console.c>.
+const MAX_WRITE_LEN: usize = 32 * 1024;
+
+static VIRTIO_PORT_MAJOR: Once<MajorIdOwner> = Once::new();
+
+/// The ports that have been found, where the minor ID of a port is its index.
+static VIRTIO_PORTS: Mutex<Vec<Arc<VirtioPort>>> = Mutex::new(Vec::new());
+
+struct VirtioPort {
+    port: Arc<ConsolePort>,
+    id: DeviceId,
+    state: Mutex<VirtioPortState>,
+    buffer: SpinLock<RingBuffer<u8>, LocalIrqDisabled>,
+    pollee: Pollee,
+    weak_self: Weak<Self>,
+}
+
+#[derive(Default)]
+struct VirtioPortState {
+    /// Whether the device is registered
+    is_registered: bool,
+    /// The name of the symlink under `/dev/virtio-ports`
+    link_name: Option<String>,
+    /// Whether the device is opened
+    is_opened: bool,
+}
+
+impl VirtioPort {
+    fn new(port: Arc<ConsolePort>, minor: u32) -> Arc<Self> {
+        let major = VIRTIO_PORT_MAJOR.get().unwrap().get();
+        let id = DeviceId::new(major, MinorId::new(minor));
+
+        let vport = Arc::new_cyclic(|weak_self| Self {
+            port,
+            id,
+            state: Mutex::new(VirtioPortState::default()),
+            buffer: SpinLock::new(RingBuffer::new(BUFFER_CAPACITY)),
+            pollee: Pollee::new(),
+            weak_self: weak_self.clone(),
+        });
+
+        let vport_cloned = vport.clone();
+        vport.port.register_callback(Box::leak(Box::new(
+            move |mut reader: VmReader<Infallible>| {
+                let mut chs = vec![0u8; reader.remain()];
+                reader.read(&mut VmWriter::from(chs.as_mut_slice()));
+                vport_cloned.receive(chs.as_slice());
+            },
+        )));
+
+        vport
+    }
+
+    /// Receives the data from the host.
+    fn receive(&self, chs: &[u8]) {
+        // The data of the console ports are received by the `hvc<N>` devices.
+        if self.port.is_console() {
+            return;
+        }
+
+        let mut buffer = self.buffer.lock();
+        // TODO: Stop receiving the data from the host instead of dropping the data when the buffer
+        // is full.
+        let len = chs.len().min(buffer.free_len());
+        if len == 0 {
+            return;
+        }
+        buffer.push_slice(&chs[..len]).unwrap();
+        drop(buffer);
+
+        self.pollee.notify(IoEvents::IN);
+    }
+
+    /// Updates the device according to the state of the port.
+    ///
+    /// The device is registered if the port is present and unregistered otherwise. The symlink
+    /// under `/dev/virtio-ports` is added if the port has a name.
+    fn update(&self) -> Result<()> {
+        let mut state = self.state.lock();
+
+        let is_present = self.port.is_present();
+        if is_present != state.is_registered {
+            if is_present {
+                char::register(self.weak_self.upgrade().unwrap())?;
+            } else {
+                char::unregister(self.id)?;
+            }
+            state.is_registered = is_present;
+        }
+
+        let link_name = self.port.name().filter(|_| is_present);
+        if link_name != state.link_name {
+            if let Some(old_name) = state.link_name.take() {
+                let _ = devtmpfs::remove_symlink(&format!("virtio-ports/{}", old_name));
+            }
+            if let Some(name) = link_name.as_ref() {
+                self.add_symlink(name);
+            }
+            state.link_name = link_name;
+        }
+
+        drop(state);
+
+        self.pollee
+            .notify(IoEvents::IN | IoEvents::OUT | IoEvents::HUP);
+
+        Ok(())
+    }
+
+    fn add_symlink(&self, name: &str) {
+        if name.is_empty() || name.contains('/') || name == "." || name == ".." {
+            warn!("the name of the virtio console port is invalid: {:?}", name);
+            return;
+        }
+
+        let path = format!("virtio-ports/{}", name);
+        let target = format!("../{}", self.devtmpfs_path().unwrap());
+        if let Err(err) = devtmpfs::add_symlink(&path, &target) {
+            warn!("failed to add the symlink '{}': {:?}", path, err);
+        }
+    }
+
+    fn check_io_events(&self) -> IoEvents {
+        let is_host_connected = self.port.is_host_connected();
+
+        let mut events = IoEvents::empty();
+        if !self.buffer.lock().is_empty() || !is_host_connected {
+            events |= IoEvents::IN;
+        }
+        if is_host_connected {
+            events |= IoEvents::OUT;
+        } else {
+            events |= IoEvents::HUP;
+        }
+
+        events
+    }
+
+    fn try_read(&self, writer: &mut VmWriter) -> Result<usize> {
+        if !writer.has_avail() {
+            return Ok(0);
+        }
+
+        let mut chs = vec![0u8; writer.avail().min(BUFFER_CAPACITY)];
+
+        let mut buffer = self.buffer.lock();
+        let len = chs.len().min(buffer.len());
+        if len == 0 {
+            // The end of file is met if the host side is disconnected.
+            if !self.port.is_host_connected() {
+                return Ok(0);
+            }
+            return_errno_with_message!(Errno::EAGAIN, "no data is received from the host");
+        }
+        buffer.pop_slice(&mut chs[..len]).unwrap();
+        drop(buffer);
+
+        self.pollee.invalidate();
+
+        match writer.write_fallible(&mut VmReader::from(&chs[..len])) {
+            Ok(len) => Ok(len),
+            Err((err, 0)) => Err(err.into()),
+            Err((_, len)) => Ok(len),
+        }
+    }
+
+    fn try_write(&self, reader: &mut VmReader) -> Result<usize> {
+        if !self.port.is_present() {
+            return_errno_with_message!(Errno::ENODEV, "the port has been removed");
+        }
+        if !self.port.is_host_connected() {
+            return_errno_with_message!(Errno::EAGAIN, "the host side is not connected");
+        }
+
+        let mut chs = vec![0u8; reader.remain().min(MAX_WRITE_LEN)];
+        let len = reader.read_fallible(&mut VmWriter::from(chs.as_mut_slice()))?;
+        self.port.send(&chs[..len]);
+
+        Ok(len)
+    }
+}
+
+impl Device for VirtioPort {
+    fn type_(&self) -> DeviceType {
+        DeviceType::Char
+    }
+
+    fn id(&self) -> DeviceId {
+        self.id
+    }
+
+    fn devtmpfs_path(&self) -> Option<String> {
+        Some(format!(
+            "vport{}p{}",
+            self.port.device_index(),
+            self.port.id()
+        ))
+    }
+
+    fn class(&self) -> &'static str {
+        "virtio-ports"
+    }
+
+    fn open(&self) -> Result<Box<dyn FileIo>> {
+        if !self.port.is_present() {
+            return_errno_with_message!(Errno::ENXIO, "the port has been removed");
+        }
+        if self.port.is_console() {
+            return_errno_with_message!(Errno::ENXIO, "the console port can only be opened as hvc");
+        }
+
+        let mut state = self.state.lock();
+        if state.is_opened {
+            return_errno_with_message!(Errno::EBUSY, "the port is already opened");
+        }
+        state.is_opened = true;
+        drop(state);
+
+        self.port.set_guest_connected(true);
+
+        Ok(Box::new(VirtioPortFile {
+            vport: self.weak_self.upgrade().unwrap(),
+        }))
+    }
+}
+
+struct VirtioPortFile {
+    vport: Arc<VirtioPort>,
+}
+
+impl Pollable for VirtioPortFile {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.vport
+            .pollee
+            .poll_with(mask, poller, || self.vport.check_io_events())
+    }
+}
+
+impl InodeIo for VirtioPortFile {
+    fn read_at(
+        &self,
+        _offset: usize,
+        writer: &mut VmWriter,
+        status_flags: StatusFlags,
+    ) -> Result<usize> {
+        if status_flags.contains(StatusFlags::O_NONBLOCK) {
+            self.vport.try_read(writer)
+        } else {
+            self.wait_events(IoEvents::IN, None, || self.vport.try_read(writer))
+        }
+    }
+
+    fn write_at(
+        &self,
+        _offset: usize,
+        reader: &mut VmReader,
+        status_flags: StatusFlags,
+    ) -> Result<usize> {
+        if status_flags.contains(StatusFlags::O_NONBLOCK) {
+            self.vport.try_write(reader)
+        } else {
+            self.wait_events(IoEvents::OUT, None, || self.vport.try_write(reader))
+        }
+    }
+}
+
+impl FileIo for VirtioPortFile {
+    fn check_seekable(&self) -> Result<()> {
+        return_errno_with_message!(Errno::ESPIPE, "seek is not supported")
+    }
+
+    fn is_offset_aware(&self) -> bool {
+        false
+    }
+}
+
+impl Drop for VirtioPortFile {
+    fn drop(&mut self) {
+        self.vport.state.lock().is_opened = false;
+        self.vport.port.set_guest_connected(false);
+    }
+}
+
+/// Updates the device of the virtio console port, creating the device if it does not exist.
+fn update_port(port: &Arc<ConsolePort>) -> Result<()> {
+    let mut vports = VIRTIO_PORTS.lock();
+    let vport = match vports.iter().find(|vport| Arc::ptr_eq(&vport.port, port)) {
+        Some(vport) => vport.clone(),
+        None => {
+            let vport = VirtioPort::new(port.clone(), vports.len() as u32);
+            vports.push(vport.clone());
+            vport
+        }
+    };
+    drop(vports);
+
+    vport.update()
+}
+
+pub(super) fn init_in_first_kthread() {
+    VIRTIO_PORT_MAJOR.call_once(|| char::allocate_major().unwrap());
+
+    // The ports that are found later are updated in the work queue, since registering a char
+    // device cannot be done in the interrupt context.
+    for port in console::all_ports() {
+        if let Err(err) = update_port(&port) {
+            warn!("failed to add the virtio console port: {:?}", err);
+        }
+    }
+
+    console::register_port_handler(|port: &Arc<ConsolePort>| {
+        let port = port.clone();
+        let work_item = WorkItem::new(Box::new(move || {
+            if let Err(err) = update_port(&port) {
+                warn!("failed to update the virtio console port: {:?}", err);
+            }
+        }));
+        submit_work_item(work_item, WorkPriority::High);
+    });
+}
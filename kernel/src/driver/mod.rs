@@ -1,8 +1,5 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use alloc::string::ToString;
-
-use aster_framebuffer::{CONSOLE_NAME, FRAMEBUFFER_CONSOLE};
 use log::info;
 
 pub fn init() {
@@ -15,8 +12,4 @@ pub fn init() {
     // the input core. We should find a way to avoid this in the future.
     #[expect(unused_imports)]
     use aster_i8042::*;
-
-    if let Some(console) = FRAMEBUFFER_CONSOLE.get() {
-        aster_console::register_device(CONSOLE_NAME.to_string(), console.clone());
-    }
 }
@@ -1,9 +1,11 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use int_to_c_enum::TryFromInt;
+
 /// Error number.
 #[expect(clippy::upper_case_acronyms)]
 #[repr(i32)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, TryFromInt)]
 pub enum Errno {
     EPERM = 1,    /* Operation not permitted */
     ENOENT = 2,   /* No such file or directory */
@@ -353,6 +355,62 @@ impl From<aster_systree::Error> for Error {
     }
 }
 
+impl From<aster_time::RtcError> for Error {
+    fn from(err: aster_time::RtcError) -> Self {
+        match err {
+            aster_time::RtcError::NoDevice => {
+                Error::with_message(Errno::ENODEV, "no RTC devices exist")
+            }
+            aster_time::RtcError::Unsupported => {
+                Error::with_message(Errno::EINVAL, "the operation is not supported by the RTC")
+            }
+            aster_time::RtcError::InvalidTime => {
+                Error::with_message(Errno::EINVAL, "the time is not supported by the RTC")
+            }
+        }
+    }
+}
+
+impl From<aster_virtio::device::gpu::GpuError> for Error {
+    fn from(err: aster_virtio::device::gpu::GpuError) -> Self {
+        use aster_virtio::device::gpu::GpuError;
+
+        match err {
+            GpuError::OutOfMemory => Error::with_message(Errno::ENOMEM, "the GPU is out of memory"),
+            GpuError::InvalidScanoutId
+            | GpuError::InvalidResourceId
+            | GpuError::InvalidContextId
+            | GpuError::InvalidParameter => {
+                Error::with_message(Errno::EINVAL, "the GPU rejects the command")
+            }
+            GpuError::Unspecified => Error::with_message(Errno::EIO, "the GPU command fails"),
+        }
+    }
+}
+
+impl From<aster_virtio::device::sound::SoundError> for Error {
+    fn from(err: aster_virtio::device::sound::SoundError) -> Self {
+        use aster_virtio::device::sound::SoundError;
+
+        match err {
+            SoundError::BadMessage => {
+                Error::with_message(Errno::EINVAL, "the sound device rejects the request")
+            }
+            SoundError::NotSupported => Error::with_message(
+                Errno::EOPNOTSUPP,
+                "the sound device does not support the request",
+            ),
+            SoundError::IoError => Error::with_message(Errno::EIO, "the sound device fails"),
+            SoundError::OutOfMemory => {
+                Error::with_message(Errno::ENOMEM, "the sound buffers cannot be allocated")
+            }
+            SoundError::QueueFull => {
+                Error::with_message(Errno::EBUSY, "too many sound buffers are pending")
+            }
+        }
+    }
+}
+
 impl From<aster_util::printer::VmPrinterError> for Error {
     fn from(value: aster_util::printer::VmPrinterError) -> Self {
         match value {
@@ -152,7 +152,7 @@ impl dyn FileLike {
 /// An object that may be memory mapped into the user address space.
 #[derive(Debug, Clone)]
 pub enum Mappable {
-    /// A VMO (i.e., page cache).
+    /// A VMO (e.g., page cache).
     Vmo(Arc<Vmo>),
     /// An MMIO region.
     IoMem(IoMem),
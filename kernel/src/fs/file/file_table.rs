@@ -3,6 +3,9 @@
 use core::sync::atomic::{AtomicU8, Ordering};
 
 use aster_util::slot_vec::SlotVec;
+use ostd::sync::{
+    PreemptDisabled, Rcu, RcuOption, RoArc, RwArc, RwLockReadGuard, RwLockWriteGuard, rcu_read_lock,
+};
 
 use super::{StatusFlags, file_handle::FileLike};
 use crate::{
@@ -17,15 +20,17 @@ use crate::{
 
 pub type FileDesc = i32;
 
-#[derive(Clone)]
 pub struct FileTable {
     table: SlotVec<FileTableEntry>,
+    /// The files published for the lockless lookups via [`SharedFileTable::get_file`].
+    view: Arc<FileTableView>,
 }
 
 impl FileTable {
-    pub const fn new() -> Self {
+    pub fn new() -> Self {
         Self {
             table: SlotVec::new(),
+            view: Arc::new(FileTableView::new()),
         }
     }
 
@@ -59,6 +64,7 @@ impl FileTable {
         };
 
         let min_free_fd = get_min_free_fd();
+        self.view.set(min_free_fd, Some(entry.file()));
         self.table.put_at(min_free_fd, entry);
         Ok(min_free_fd as FileDesc)
     }
@@ -72,6 +78,7 @@ impl FileTable {
     ) -> Result<Option<Arc<dyn FileLike>>> {
         let entry = self.duplicate_entry(fd, flags)?;
         let closed_file = self.close_file(new_fd);
+        self.view.set(new_fd as usize, Some(entry.file()));
         self.table.put_at(new_fd as usize, entry);
         Ok(closed_file)
     }
@@ -86,12 +93,15 @@ impl FileTable {
     }
 
     pub fn insert(&mut self, item: Arc<dyn FileLike>, flags: FdFlags) -> FileDesc {
-        let entry = FileTableEntry::new(item, flags);
-        self.table.put(entry) as FileDesc
+        let entry = FileTableEntry::new(item.clone(), flags);
+        let fd = self.table.put(entry);
+        self.view.set(fd, Some(&item));
+        fd as FileDesc
     }
 
     pub fn close_file(&mut self, fd: FileDesc) -> Option<Arc<dyn FileLike>> {
         let removed_entry = self.table.remove(fd as usize)?;
+        self.view.set(fd as usize, None);
         // POSIX record locks are process-associated and Linux drops them when any fd for the inode is
         // closed by that process, even if duplicated descriptors still exist.
         //
@@ -102,6 +112,18 @@ impl FileTable {
         Some(removed_entry.file)
     }
 
+    /// Releases the POSIX record locks of the current process on all the files.
+    ///
+    /// The locks are owned by the process, so they must be released when the process
+    /// exits, even if the files are still opened by other processes.
+    pub fn release_range_locks(&self) {
+        for entry in self.table.iter() {
+            if let Ok(inode_handle) = entry.file.as_inode_handle_or_err() {
+                inode_handle.release_range_locks();
+            }
+        }
+    }
+
     pub fn close_files_on_exec(&mut self) -> Vec<Arc<dyn FileLike>> {
         self.close_files(|entry| entry.flags().contains(FdFlags::CLOEXEC))
     }
@@ -162,24 +184,121 @@ impl Default for FileTable {
     }
 }
 
-/// A helper trait that provides methods to operate the file table.
-pub trait WithFileTable {
-    /// Calls `f` with the file table.
+impl Clone for FileTable {
+    fn clone(&self) -> Self {
+        let table = self.table.clone();
+
+        let view = FileTableView::new();
+        for (fd, entry) in table.idxes_and_items() {
+            view.set(fd, Some(entry.file()));
+        }
+
+        Self {
+            table,
+            view: Arc::new(view),
+        }
+    }
+}
+
+/// A file table that can be shared among threads.
+///
+/// The file table is protected by a read-write lock. But the files can also be looked up without
+/// locking via [`Self::get_file`], because they are published via RCU whenever the file table is
+/// changed.
+#[derive(Clone)]
+pub struct SharedFileTable {
+    table: RwArc<FileTable>,
+    view: Arc<FileTableView>,
+}
+
+impl SharedFileTable {
+    pub fn new(table: FileTable) -> Self {
+        let view = table.view.clone();
+        Self {
+            table: RwArc::new(table),
+            view,
+        }
+    }
+
+    /// Acquires the read lock on the file table.
+    pub fn read(&self) -> RwLockReadGuard<'_, FileTable, PreemptDisabled> {
+        self.table.read()
+    }
+
+    /// Acquires the write lock on the file table.
+    pub fn write(&self) -> RwLockWriteGuard<'_, FileTable, PreemptDisabled> {
+        self.table.write()
+    }
+
+    /// Returns a reference to the file table if it is not shared with others.
     ///
-    /// This method is lockless if the file table is not shared. Otherwise, `f` is called while
-    /// holding the read lock on the file table.
-    fn read_with<R>(&mut self, f: impl FnOnce(&FileTable) -> R) -> R;
+    /// See [`RwArc::get`].
+    pub fn get(&mut self) -> Option<&FileTable> {
+        self.table.get()
+    }
+
+    /// Creates a new read-only reference to the file table.
+    pub fn clone_ro(&self) -> RoArc<FileTable> {
+        self.table.clone_ro()
+    }
+
+    /// Gets a file from a file descriptor without locking.
+    pub fn get_file(&self, fd: FileDesc) -> Result<Arc<dyn FileLike>> {
+        usize::try_from(fd)
+            .ok()
+            .and_then(|fd| self.view.get(fd))
+            .ok_or(Error::with_message(Errno::EBADF, "fd not exits"))
+    }
 }
 
-impl WithFileTable for FileTableRefMut<'_> {
-    fn read_with<R>(&mut self, f: impl FnOnce(&FileTable) -> R) -> R {
-        let file_table = self.unwrap();
+/// The files in a [`FileTable`] that can be read via RCU.
+///
+/// The slots hold weak references, so that the last reference to a closed file is never dropped
+/// in the RCU callbacks. The writers are serialized by the lock on the file table.
+struct FileTableView {
+    slots: Rcu<Box<Vec<FileSlot>>>,
+}
 
-        if let Some(inner) = file_table.get() {
-            f(inner)
-        } else {
-            f(&file_table.read())
+type FileSlot = RcuOption<Arc<Weak<dyn FileLike>>>;
+
+impl FileTableView {
+    fn new() -> Self {
+        Self {
+            slots: Rcu::new(Box::new(Vec::new())),
+        }
+    }
+
+    fn get(&self, fd: usize) -> Option<Arc<dyn FileLike>> {
+        let guard = rcu_read_lock();
+        let slots = self.slots.read_with(&guard);
+        let file = slots.get(fd)?.read_with(&guard)?;
+        file.upgrade()
+    }
+
+    fn set(&self, fd: usize, file: Option<&Arc<dyn FileLike>>) {
+        let file = file.map(|file| Arc::new(Arc::downgrade(file)));
+
+        let guard = rcu_read_lock();
+        let slots = self.slots.read_with(&guard);
+        if let Some(slot) = slots.get(fd) {
+            slot.update(file);
+            return;
+        }
+        if file.is_none() {
+            return;
         }
+
+        // Grow the slots by publishing a larger copy. The old slots may still be read, so the
+        // files in them are shared rather than moved.
+        let new_len = (fd + 1).next_power_of_two();
+        let mut new_slots: Vec<FileSlot> = slots
+            .iter()
+            .map(|slot| RcuOption::new(slot.read_with(&guard).map(|file| (*file).clone())))
+            .collect();
+        new_slots.resize_with(new_len, RcuOption::new_none);
+        new_slots[fd] = RcuOption::new(file);
+
+        self.slots.update(Box::new(new_slots));
     }
 }
 
@@ -190,11 +309,11 @@ impl WithFileTable for FileTableRefMut<'_> {
 /// exclusivity can be useful for achieving lockless file lookups.
 ///
 /// If the file table is not shared with another thread, this macro will be free of locks
-/// ([`RwArc::read`]) and free of reference counting ([`Arc::clone`]).
+/// ([`SharedFileTable::read`]) and free of reference counting ([`Arc::clone`]).
 ///
-/// If the file table is shared, the read lock is taken, the file is cloned, and then the read lock
-/// is released. Cloning and releasing the lock is necessary because we cannot hold such locks when
-/// operating on files, since many operations on files can block.
+/// If the file table is shared, the file is looked up without locking via
+/// [`SharedFileTable::get_file`] and then cloned. Cloning is necessary because the file may be
+/// closed by another thread while it is being operated on.
 ///
 /// Note: This has to be a macro due to a limitation in the Rust borrow check implementation. Once
 /// <https://github.com/rust-lang/rust/issues/58910> is fixed, we can try to convert this macro to
@@ -202,27 +321,25 @@ impl WithFileTable for FileTableRefMut<'_> {
 ///
 /// [`RefCell`]: core::cell::RefCell
 /// [`ThreadLocal`]: crate::process::posix_thread::ThreadLocal
-/// [`RwArc::read`]: ostd::sync::RwArc::read
 macro_rules! get_file_fast {
     ($file_table:expr, $file_desc:expr) => {{
         use alloc::borrow::Cow;
 
-        use ostd::sync::RwArc;
         use $crate::{
-            fs::file::file_table::{FileDesc, FileTable},
+            fs::file::file_table::{FileDesc, SharedFileTable},
             process::posix_thread::FileTableRefMut,
         };
 
         let file_table: &mut FileTableRefMut<'_> = $file_table;
-        let file_table: &mut RwArc<FileTable> = file_table.unwrap();
+        let file_table: &mut SharedFileTable = file_table.unwrap();
         let file_desc: FileDesc = $file_desc;
 
         if let Some(inner) = file_table.get() {
             // Fast path: The file table is not shared, we can get the file in a lockless way.
             Cow::Borrowed(inner.get_file(file_desc)?)
         } else {
-            // Slow path: The file table is shared, we need to hold the lock and clone the file.
-            Cow::Owned(file_table.read().get_file(file_desc)?.clone())
+            // Slow path: The file table is shared, we need to look up the file via RCU and clone it.
+            Cow::Owned(file_table.get_file(file_desc)?)
         }
     }};
 }
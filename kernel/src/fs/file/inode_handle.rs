@@ -16,15 +16,25 @@ use crate::{
         pipe::PipeHandle,
         utils::DirentVisitor,
         vfs::{
+            fscrypt,
             inode::{FallocMode, InodeIo},
             inode_ext::InodeExt,
             path::Path,
-            range_lock::{FileRange, OFFSET_MAX, RangeLockItem, RangeLockType},
+            range_lock::{
+                FileRange, OFFSET_MAX, RangeLockItem, RangeLockOwner, RangeLockType,
+                RangeLockWaitMode,
+            },
         },
     },
     prelude::*,
-    process::signal::{PollHandle, Pollable},
-    util::ioctl::RawIoctl,
+    process::{
+        Process,
+        credentials::capabilities::CapSet,
+        posix_thread::AsPosixThread,
+        signal::{PollHandle, Pollable},
+    },
+    security::lsm,
+    util::ioctl::{RawIoctl, dispatch_ioctl},
 };
 
 pub struct InodeHandle {
@@ -40,12 +50,12 @@ pub struct InodeHandle {
 
 impl InodeHandle {
     pub fn new(path: Path, access_mode: AccessMode, status_flags: StatusFlags) -> Result<Self> {
-        let inode = path.inode();
         if !status_flags.contains(StatusFlags::O_PATH) {
             // "Opening a file or directory with the O_PATH flag requires no permissions on the
             // object itself".
             // Reference: <https://man7.org/linux/man-pages/man2/openat.2.html>
-            inode.check_permission(access_mode.into())?;
+            path.check_permission(access_mode.into())?;
+            lsm::file_open(&path, access_mode)?;
         }
 
         Self::new_unchecked_access(path, access_mode, status_flags)
@@ -64,6 +74,12 @@ impl InodeHandle {
         } else {
             let file_io = inode.open(access_mode, status_flags).transpose()?;
             let rights = Rights::from(access_mode);
+            if inode.type_().is_regular_file() {
+                inode
+                    .fs_lock_context_or_init()
+                    .lease_list()
+                    .on_open(rights.contains(Rights::WRITE));
+            }
             (file_io, rights)
         };
 
@@ -147,7 +163,7 @@ impl InodeHandle {
         Ok(req_lock)
     }
 
-    pub fn set_range_lock(&self, lock: &RangeLockItem, is_nonblocking: bool) -> Result<()> {
+    pub fn set_range_lock(&self, lock: &RangeLockItem, wait_mode: RangeLockWaitMode) -> Result<()> {
         match lock.type_() {
             RangeLockType::ReadLock => {
                 if !self.rights.contains(Rights::READ) {
@@ -176,7 +192,7 @@ impl InodeHandle {
             .inode()
             .fs_lock_context_or_init()
             .range_lock_list();
-        range_lock_list.set_lock(lock, is_nonblocking)
+        range_lock_list.set_lock(lock, wait_mode)
     }
 
     pub fn release_range_locks(&self) {
@@ -187,6 +203,20 @@ impl InodeHandle {
         self.unlock_range_lock(&range_lock);
     }
 
+    /// Returns the owner of the open file description locks set through this handle.
+    pub fn ofd_lock_owner(&self) -> RangeLockOwner {
+        RangeLockOwner::OpenFile(self as *const Self as usize)
+    }
+
+    fn release_ofd_locks(&self) {
+        let range_lock = RangeLockItem::new_with_owner(
+            RangeLockType::Unlock,
+            FileRange::new(0, OFFSET_MAX).unwrap(),
+            self.ofd_lock_owner(),
+        );
+        self.unlock_range_lock(&range_lock);
+    }
+
     fn unlock_range_lock(&self, lock: &RangeLockItem) {
         if let Some(range_lock_list) = self
             .path
@@ -219,6 +249,48 @@ impl InodeHandle {
         Ok(())
     }
 
+    /// Sets, changes or removes the lease on the file.
+    ///
+    /// The process is notified with `SIGIO` when the lease is broken.
+    pub fn set_lease(&self, type_: RangeLockType, process: Weak<Process>) -> Result<()> {
+        if self.rights.is_empty() {
+            return_errno_with_message!(Errno::EBADF, "the file is opened as a path");
+        }
+
+        let inode = self.path.inode();
+        if !inode.type_().is_regular_file() {
+            return_errno_with_message!(Errno::EINVAL, "the file is not a regular file");
+        }
+
+        if type_ != RangeLockType::Unlock {
+            let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+            if credentials.fsuid() != self.path.owner()?
+                && !credentials.effective_capset().contains(CapSet::LEASE)
+            {
+                return_errno_with_message!(Errno::EACCES, "only the owner can set a lease");
+            }
+        }
+
+        inode.fs_lock_context_or_init().lease_list().set_lease(
+            self,
+            type_,
+            process,
+            self.rights.contains(Rights::WRITE),
+        )
+    }
+
+    /// Returns the type of the lease on the file.
+    pub fn get_lease(&self) -> Result<RangeLockType> {
+        if self.rights.is_empty() {
+            return_errno_with_message!(Errno::EBADF, "the file is opened as a path");
+        }
+
+        let Some(lease_list) = self.path.inode().fs_lock_context().map(|c| c.lease_list()) else {
+            return Ok(RangeLockType::Unlock);
+        };
+        Ok(lease_list.get_lease(self))
+    }
+
     pub fn downcast_file_io<T: 'static>(&self) -> Result<Option<&T>> {
         if self.rights.is_empty() {
             return_errno_with_message!(Errno::EBADF, "the file is opened as a path");
@@ -326,6 +398,8 @@ impl FileLike for InodeHandle {
     }
 
     fn ioctl(&self, raw_ioctl: RawIoctl) -> Result<i32> {
+        use ioctl_defs::*;
+
         if self.rights.is_empty() {
             return_errno_with_message!(Errno::EBADF, "the file is opened as a path");
         }
@@ -334,7 +408,24 @@ impl FileLike for InodeHandle {
             return file_io.ioctl(raw_ioctl);
         }
 
-        return_errno_with_message!(Errno::ENOTTY, "ioctl is not supported");
+        dispatch_ioctl!(match raw_ioctl {
+            cmd @ FiTrim => {
+                let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+                if !credentials.effective_capset().contains(CapSet::SYS_ADMIN) {
+                    return_errno_with_message!(
+                        Errno::EPERM,
+                        "trimming the file system requires CAP_SYS_ADMIN"
+                    );
+                }
+
+                let mut range = cmd.read()?;
+                let fs = self.path.inode().fs();
+                range.len = fs.trim(range.start, range.len, range.min_len)?;
+                cmd.write(&range)?;
+                Ok(0)
+            }
+            _ => fscrypt::ioctl(self.path.inode(), raw_ioctl),
+        })
     }
 
     fn mappable(&self) -> Result<Mappable> {
@@ -368,6 +459,7 @@ impl FileLike for InodeHandle {
             // FIXME: It's allowed to `ftruncate` an append-only file on Linux.
             return_errno_with_message!(Errno::EPERM, "can not resize append-only file");
         }
+        lsm::path_truncate(&self.path)?;
         self.path.inode().resize(new_size)
     }
 
@@ -496,7 +588,18 @@ impl FileLike for InodeHandle {
 impl Drop for InodeHandle {
     fn drop(&mut self) {
         self.release_range_locks();
+        self.release_ofd_locks();
         let _ = self.unlock_flock();
+
+        let inode = self.path.inode();
+        if !self.rights.is_empty()
+            && inode.type_().is_regular_file()
+            && let Some(lock_context) = inode.fs_lock_context()
+        {
+            lock_context
+                .lease_list()
+                .on_close(self, self.rights.contains(Rights::WRITE));
+        }
     }
 }
 
@@ -511,6 +614,23 @@ impl Debug for InodeHandle {
     }
 }
 
+mod ioctl_defs {
+    use super::FstrimRange;
+    use crate::util::ioctl::{InOutData, ioc};
+
+    // Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/fs.h>
+    pub(super) type FiTrim = ioc!(FITRIM, b'X', 121, InOutData<FstrimRange>);
+}
+
+/// The argument of `FITRIM`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct FstrimRange {
+    start: u64,
+    len: u64,
+    min_len: u64,
+}
+
 /// Describes the position to seek from.
 #[derive(Copy, PartialEq, Eq, Clone, Debug)]
 pub enum SeekFrom {
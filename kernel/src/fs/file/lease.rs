@@ -0,0 +1,283 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! File leases, which are set by `fcntl(F_SETLEASE)`.
+//!
+//! The holder of a lease is notified with `SIGIO` when another process opens the file
+//! in a conflicting mode or truncates it. The process that breaks the lease is blocked
+//! until the holder downgrades or removes the lease. If the holder does not respond
+//! within the lease-break time, the lease is broken forcibly.
+//!
+//! Reference: <https://man7.org/linux/man-pages/man2/F_SETLEASE.2const.html>
+
+use core::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use ostd::sync::WaitQueue;
+
+use super::InodeHandle;
+use crate::{
+    fs::vfs::{inode::Inode, inode_ext::InodeExt, range_lock::RangeLockType},
+    prelude::*,
+    process::{Process, signal::constants::SIGIO},
+};
+
+/// The time after which a lease is broken forcibly, which is the default of Linux.
+const LEASE_BREAK_TIME: Duration = Duration::from_secs(45);
+
+/// A lease on a file.
+struct Lease {
+    /// The open file description that owns the lease, which is identified by the
+    /// address of its `InodeHandle`.
+    owner: usize,
+    type_: RangeLockType,
+    /// The type that the lease is being broken to, if a lease break is in progress.
+    breaking_to: Option<RangeLockType>,
+    /// The process that is notified when the lease is broken.
+    process: Weak<Process>,
+}
+
+impl Lease {
+    /// Returns whether the lease conflicts with an open in the given mode.
+    fn conflict_with_open(&self, is_write: bool) -> bool {
+        is_write || self.type_ == RangeLockType::WriteLock
+    }
+}
+
+/// The leases on a file.
+///
+/// The opened file descriptions of the file are counted here, since a lease can only
+/// be set if there are no conflicting opens.
+pub struct LeaseList {
+    leases: Mutex<Vec<Lease>>,
+    /// The number of the open file descriptions of the file.
+    nr_opened: AtomicUsize,
+    /// The number of the writable open file descriptions of the file.
+    nr_writable: AtomicUsize,
+    /// The wait queue of the threads waiting for the leases to be broken.
+    wait_queue: WaitQueue,
+}
+
+impl LeaseList {
+    pub fn new() -> Self {
+        Self {
+            leases: Mutex::new(Vec::new()),
+            nr_opened: AtomicUsize::new(0),
+            nr_writable: AtomicUsize::new(0),
+            wait_queue: WaitQueue::new(),
+        }
+    }
+
+    /// Records that an open file description of the file is created.
+    pub(super) fn on_open(&self, is_writable: bool) {
+        self.nr_opened.fetch_add(1, Ordering::Relaxed);
+        if is_writable {
+            self.nr_writable.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Records that an open file description of the file is dropped, which removes
+    /// its lease.
+    pub(super) fn on_close(&self, handle: &InodeHandle, is_writable: bool) {
+        self.nr_opened.fetch_sub(1, Ordering::Relaxed);
+        if is_writable {
+            self.nr_writable.fetch_sub(1, Ordering::Relaxed);
+        }
+
+        let owner = handle as *const InodeHandle as usize;
+        let mut leases = self.leases.lock();
+        if let Some(idx) = leases.iter().position(|lease| lease.owner == owner) {
+            leases.remove(idx);
+            self.wait_queue.wake_all();
+        }
+    }
+
+    /// Returns the type of the lease owned by the open file description.
+    ///
+    /// If the lease is being broken, the type that it is being broken to is returned.
+    pub(super) fn get_lease(&self, handle: &InodeHandle) -> RangeLockType {
+        let owner = handle as *const InodeHandle as usize;
+        let leases = self.leases.lock();
+        match leases.iter().find(|lease| lease.owner == owner) {
+            Some(lease) => lease.breaking_to.unwrap_or(lease.type_),
+            None => RangeLockType::Unlock,
+        }
+    }
+
+    /// Sets, changes or removes the lease owned by the open file description.
+    pub(super) fn set_lease(
+        &self,
+        handle: &InodeHandle,
+        type_: RangeLockType,
+        process: Weak<Process>,
+        is_writable: bool,
+    ) -> Result<()> {
+        let owner = handle as *const InodeHandle as usize;
+        let mut leases = self.leases.lock();
+        let idx = leases.iter().position(|lease| lease.owner == owner);
+
+        if type_ == RangeLockType::Unlock {
+            if let Some(idx) = idx {
+                leases.remove(idx);
+                self.wait_queue.wake_all();
+            }
+            return Ok(());
+        }
+
+        // During a lease break, the lease can only be downgraded to the type that it is
+        // being broken to.
+        if let Some(idx) = idx
+            && let Some(breaking_to) = leases[idx].breaking_to
+            && type_ != breaking_to
+        {
+            return_errno_with_message!(Errno::EAGAIN, "the lease is being broken");
+        }
+
+        let has_other_leases = leases.iter().any(|lease| lease.owner != owner);
+        match type_ {
+            RangeLockType::ReadLock => {
+                let nr_other_writable =
+                    self.nr_writable.load(Ordering::Relaxed) - is_writable as usize;
+                if nr_other_writable > 0 {
+                    return_errno_with_message!(Errno::EAGAIN, "the file is opened for writing");
+                }
+                if leases
+                    .iter()
+                    .any(|lease| lease.owner != owner && lease.type_ == RangeLockType::WriteLock)
+                {
+                    return_errno_with_message!(Errno::EAGAIN, "the file has a write lease");
+                }
+            }
+            RangeLockType::WriteLock => {
+                if self.nr_opened.load(Ordering::Relaxed) > 1 {
+                    return_errno_with_message!(Errno::EAGAIN, "the file is opened elsewhere");
+                }
+                if has_other_leases {
+                    return_errno_with_message!(Errno::EAGAIN, "the file has other leases");
+                }
+            }
+            RangeLockType::Unlock => unreachable!(),
+        }
+
+        match idx {
+            Some(idx) => {
+                let lease = &mut leases[idx];
+                lease.type_ = type_;
+                lease.breaking_to = None;
+                lease.process = process;
+                // A downgraded lease may not block the waiters anymore.
+                self.wait_queue.wake_all();
+            }
+            None => leases.push(Lease {
+                owner,
+                type_,
+                breaking_to: None,
+                process,
+            }),
+        }
+        Ok(())
+    }
+
+    /// Breaks the leases that conflict with an open in the given mode.
+    ///
+    /// The holders of the leases are notified with `SIGIO`. If `is_nonblocking` is
+    /// true, this method fails with `EAGAIN` if there are conflicting leases.
+    /// Otherwise, this method waits until the leases are downgraded or removed, or
+    /// they are broken forcibly after the lease-break time.
+    fn break_lease(&self, is_write: bool, is_nonblocking: bool) -> Result<()> {
+        let breaking_to = if is_write {
+            RangeLockType::Unlock
+        } else {
+            RangeLockType::ReadLock
+        };
+
+        {
+            let mut leases = self.leases.lock();
+            let mut has_conflicts = false;
+            for lease in leases
+                .iter_mut()
+                .filter(|lease| lease.conflict_with_open(is_write))
+            {
+                has_conflicts = true;
+                match lease.breaking_to {
+                    None => {
+                        lease.breaking_to = Some(breaking_to);
+                        crate::process::enqueue_signal_async(lease.process.clone(), SIGIO);
+                    }
+                    Some(RangeLockType::ReadLock) if is_write => {
+                        lease.breaking_to = Some(breaking_to);
+                    }
+                    Some(_) => {}
+                }
+            }
+            if !has_conflicts {
+                return Ok(());
+            }
+        }
+
+        if is_nonblocking {
+            return_errno_with_message!(Errno::EAGAIN, "the lease is being broken");
+        }
+
+        let has_no_conflicts = || {
+            let leases = self.leases.lock();
+            if leases
+                .iter()
+                .any(|lease| lease.conflict_with_open(is_write))
+            {
+                None
+            } else {
+                Some(())
+            }
+        };
+        match self
+            .wait_queue
+            .pause_until_or_timeout(has_no_conflicts, &LEASE_BREAK_TIME)
+        {
+            Err(err) if err.error() == Errno::ETIME => {
+                self.break_lease_forcibly(is_write);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Breaks the conflicting leases without waiting for their holders.
+    fn break_lease_forcibly(&self, is_write: bool) {
+        let mut leases = self.leases.lock();
+        leases.retain_mut(|lease| {
+            if !lease.conflict_with_open(is_write) {
+                return true;
+            }
+            match lease.breaking_to {
+                Some(RangeLockType::ReadLock) => {
+                    lease.type_ = RangeLockType::ReadLock;
+                    lease.breaking_to = None;
+                    true
+                }
+                _ => false,
+            }
+        });
+        self.wait_queue.wake_all();
+    }
+}
+
+impl Default for LeaseList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Breaks the leases on the inode that conflict with an open or a truncation.
+///
+/// An open for writing or a truncation breaks all the leases, while an open for
+/// reading only breaks the write leases.
+pub fn break_lease(inode: &dyn Inode, is_write: bool, is_nonblocking: bool) -> Result<()> {
+    let Some(lock_context) = inode.fs_lock_context() else {
+        return Ok(());
+    };
+    lock_context
+        .lease_list()
+        .break_lease(is_write, is_nonblocking)
+}
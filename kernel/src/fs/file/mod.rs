@@ -8,6 +8,7 @@ pub mod file_table;
 pub mod flock;
 mod inode_attr;
 mod inode_handle;
+pub mod lease;
 
 pub use file_attr::{
     access_mode::AccessMode,
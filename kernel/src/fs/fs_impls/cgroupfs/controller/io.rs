@@ -0,0 +1,640 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{
+    collections::btree_map::BTreeMap,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::{
+    sync::atomic::{AtomicU16, Ordering},
+    time::Duration,
+};
+
+use aster_block::{
+    BlockDevice, PartitionNode, SECTOR_SIZE,
+    bio::{Bio, BioType},
+};
+use aster_systree::{Error, MAX_ATTR_SIZE, Result, SysAttrSetBuilder, SysPerms, SysStr};
+use aster_util::printer::VmPrinter;
+use device_id::{DeviceId, MajorId, MinorId};
+use ostd::{
+    mm::{VmReader, VmWriter},
+    sync::{SpinLock, WaitQueue},
+};
+
+use super::SubControlStatic;
+use crate::{
+    fs::cgroupfs::{CgroupSysNode, systree_node::CgroupSystem},
+    process::Process,
+    time::{clocks::MonotonicClock, wait::WaitTimeout},
+    trace::events::block::trace_block_bio_queue,
+    util::ReadCString,
+};
+
+/// The default weight of a cgroup.
+const DEFAULT_WEIGHT: u16 = 100;
+/// The range of the valid weights.
+const WEIGHT_RANGE: core::ops::RangeInclusive<u16> = 1..=10000;
+
+/// The virtual time that a cgroup can go ahead of the others, in bytes of the default weight.
+const MAX_VTIME_LEAD: u64 = 1024 * 1024;
+/// The time after which a cgroup that submits no I/O is no longer considered active.
+const ACTIVE_PERIOD: Duration = Duration::from_millis(100);
+/// The time to wait for the lagging cgroups before they are considered idle.
+const IDLE_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// A sub-controller responsible for block I/O management in the cgroup subsystem.
+///
+/// The bios are accounted to the cgroup of the submitting process when they are submitted. The
+/// bios submitted by the kernel threads (e.g., the writeback of the page cache) are accounted to
+/// the root cgroup, and they are never throttled.
+///
+/// The limits in `io.max` are enforced at every level of the hierarchy. The weights in
+/// `io.weight` share the devices among the cgroups doing I/O on them. Unlike Linux, the weights
+/// are compared among all these cgroups, rather than among the siblings only.
+///
+/// Reference: <https://docs.kernel.org/admin-guide/cgroup-v2.html#io>
+pub struct IoController {
+    /// The I/O states of the block devices, indexed by the raw device IDs.
+    devices: SpinLock<BTreeMap<u32, DeviceIoState>>,
+    /// The weight of the devices that have no weights of their own.
+    default_weight: AtomicU16,
+}
+
+/// The I/O state of a cgroup on a block device.
+struct DeviceIoState {
+    id: DeviceId,
+    /// The statistics indexed by [`IoDirection`].
+    stats: [IoStat; 3],
+    /// The throttles of reads and writes.
+    throttles: [Throttle; 2],
+    weight: Option<u16>,
+}
+
+#[derive(Clone, Copy, Default)]
+struct IoStat {
+    nr_bytes: u64,
+    nr_ios: u64,
+}
+
+/// The throttle of the I/O in one direction.
+///
+/// Each bio reserves a period of time, whose length is its cost divided by the limit. The bio
+/// cannot be dispatched until its period begins.
+#[derive(Clone, Copy)]
+struct Throttle {
+    /// The limit of bytes per second, where `u64::MAX` means no limit.
+    bps: u64,
+    /// The limit of I/O operations per second, where `u64::MAX` means no limit.
+    iops: u64,
+    /// The end of the periods reserved according to the limit of bytes.
+    bps_reserved_until: Duration,
+    /// The end of the periods reserved according to the limit of I/O operations.
+    iops_reserved_until: Duration,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum IoDirection {
+    Read = 0,
+    Write = 1,
+    Discard = 2,
+}
+
+impl IoController {
+    pub(super) fn init_attr_set(builder: &mut SysAttrSetBuilder, is_root: bool) {
+        if !is_root {
+            builder.add(SysStr::from("io.max"), SysPerms::DEFAULT_RW_ATTR_PERMS);
+            builder.add(SysStr::from("io.weight"), SysPerms::DEFAULT_RW_ATTR_PERMS);
+        }
+
+        builder.add(SysStr::from("io.stat"), SysPerms::DEFAULT_RO_ATTR_PERMS);
+    }
+
+    /// Accounts a bio and reserves the time to dispatch it.
+    ///
+    /// Returns how long the bio should wait before it is dispatched.
+    fn charge(
+        &self,
+        device_id: DeviceId,
+        direction: IoDirection,
+        nr_bytes: u64,
+        now: Duration,
+    ) -> Duration {
+        let mut devices = self.devices.lock();
+        let state = devices
+            .entry(device_id.to_raw())
+            .or_insert_with(|| DeviceIoState::new(device_id));
+
+        let stat = &mut state.stats[direction as usize];
+        stat.nr_bytes += nr_bytes;
+        stat.nr_ios += 1;
+
+        if direction == IoDirection::Discard {
+            return Duration::ZERO;
+        }
+        state.throttles[direction as usize].reserve(nr_bytes, now)
+    }
+
+    /// Returns the weight of the block device.
+    fn weight(&self, device_id: DeviceId) -> u16 {
+        self.devices
+            .lock()
+            .get(&device_id.to_raw())
+            .and_then(|state| state.weight)
+            .unwrap_or_else(|| self.default_weight.load(Ordering::Relaxed))
+    }
+
+    fn write_max(&self, line: &str) -> Result<()> {
+        let mut tokens = line.split_whitespace();
+        let device_id = parse_device_id(tokens.next().ok_or(Error::InvalidOperation)?)?;
+
+        let mut devices = self.devices.lock();
+        let state = devices
+            .entry(device_id.to_raw())
+            .or_insert_with(|| DeviceIoState::new(device_id));
+
+        let mut throttles = state.throttles;
+        for token in tokens {
+            let (key, value) = token.split_once('=').ok_or(Error::InvalidOperation)?;
+            let value = match value {
+                "max" => u64::MAX,
+                value => match value.parse::<u64>() {
+                    Ok(value) if value > 0 => value,
+                    _ => return Err(Error::InvalidOperation),
+                },
+            };
+            match key {
+                "rbps" => throttles[IoDirection::Read as usize].bps = value,
+                "wbps" => throttles[IoDirection::Write as usize].bps = value,
+                "riops" => throttles[IoDirection::Read as usize].iops = value,
+                "wiops" => throttles[IoDirection::Write as usize].iops = value,
+                _ => return Err(Error::InvalidOperation),
+            }
+        }
+
+        // The new limits take effect from now on.
+        state.throttles = throttles.map(|throttle| Throttle::new(throttle.bps, throttle.iops));
+        Ok(())
+    }
+
+    fn write_weight(&self, line: &str) -> Result<()> {
+        let mut tokens = line.split_whitespace();
+        let (Some(first), second, None) = (tokens.next(), tokens.next(), tokens.next()) else {
+            return Err(Error::InvalidOperation);
+        };
+
+        match (first, second) {
+            ("default", Some(weight)) | (weight, None) => {
+                let weight = parse_weight(weight)?;
+                self.default_weight.store(weight, Ordering::Relaxed);
+            }
+            (device, Some(weight)) => {
+                let device_id = parse_device_id(device)?;
+                let weight = if weight == "default" {
+                    None
+                } else {
+                    Some(parse_weight(weight)?)
+                };
+
+                let mut devices = self.devices.lock();
+                devices
+                    .entry(device_id.to_raw())
+                    .or_insert_with(|| DeviceIoState::new(device_id))
+                    .weight = weight;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl DeviceIoState {
+    fn new(id: DeviceId) -> Self {
+        Self {
+            id,
+            stats: [IoStat::default(); 3],
+            throttles: [Throttle::new(u64::MAX, u64::MAX); 2],
+            weight: None,
+        }
+    }
+}
+
+impl Throttle {
+    fn new(bps: u64, iops: u64) -> Self {
+        Self {
+            bps,
+            iops,
+            bps_reserved_until: Duration::ZERO,
+            iops_reserved_until: Duration::ZERO,
+        }
+    }
+
+    fn is_unlimited(&self) -> bool {
+        self.bps == u64::MAX && self.iops == u64::MAX
+    }
+
+    /// Reserves the time for a bio of `nr_bytes`, returning how long the bio should wait.
+    fn reserve(&mut self, nr_bytes: u64, now: Duration) -> Duration {
+        fn reserve_one(
+            reserved_until: &mut Duration,
+            cost: u64,
+            limit: u64,
+            now: Duration,
+        ) -> Duration {
+            if limit == u64::MAX {
+                return Duration::ZERO;
+            }
+
+            // The time that is not used in the past cannot be saved for bursts.
+            let start = (*reserved_until).max(now);
+            let nanos = (cost as u128 * 1_000_000_000 / limit as u128).min(u64::MAX as u128);
+            *reserved_until = start + Duration::from_nanos(nanos as u64);
+            start - now
+        }
+
+        let bps_wait = reserve_one(&mut self.bps_reserved_until, nr_bytes, self.bps, now);
+        let iops_wait = reserve_one(&mut self.iops_reserved_until, 1, self.iops, now);
+        bps_wait.max(iops_wait)
+    }
+}
+
+impl IoDirection {
+    fn from_bio_type(type_: BioType) -> Option<Self> {
+        match type_ {
+            BioType::Read => Some(Self::Read),
+            BioType::Write => Some(Self::Write),
+            BioType::Discard => Some(Self::Discard),
+            BioType::Flush => None,
+        }
+    }
+}
+
+impl super::SubControl for IoController {
+    fn read_attr_at(&self, name: &str, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+        let devices = self.devices.lock();
+        match name {
+            "io.max" => {
+                for state in devices.values() {
+                    let [read, write] = &state.throttles;
+                    if read.is_unlimited() && write.is_unlimited() {
+                        continue;
+                    }
+                    writeln!(
+                        printer,
+                        "{} rbps={} wbps={} riops={} wiops={}",
+                        DisplayDeviceId(state.id),
+                        DisplayLimit(read.bps),
+                        DisplayLimit(write.bps),
+                        DisplayLimit(read.iops),
+                        DisplayLimit(write.iops),
+                    )?;
+                }
+            }
+            "io.stat" => {
+                for state in devices.values() {
+                    let [read, write, discard] = &state.stats;
+                    if read.nr_ios == 0 && write.nr_ios == 0 && discard.nr_ios == 0 {
+                        continue;
+                    }
+                    writeln!(
+                        printer,
+                        "{} rbytes={} wbytes={} rios={} wios={} dbytes={} dios={}",
+                        DisplayDeviceId(state.id),
+                        read.nr_bytes,
+                        write.nr_bytes,
+                        read.nr_ios,
+                        write.nr_ios,
+                        discard.nr_bytes,
+                        discard.nr_ios,
+                    )?;
+                }
+            }
+            "io.weight" => {
+                let default_weight = self.default_weight.load(Ordering::Relaxed);
+                writeln!(printer, "default {}", default_weight)?;
+                for state in devices.values() {
+                    if let Some(weight) = state.weight {
+                        writeln!(printer, "{} {}", DisplayDeviceId(state.id), weight)?;
+                    }
+                }
+            }
+            _ => return Err(Error::AttributeError),
+        }
+
+        Ok(printer.bytes_written())
+    }
+
+    fn write_attr(&self, name: &str, reader: &mut VmReader) -> Result<usize> {
+        let write_line: fn(&Self, &str) -> Result<()> = match name {
+            "io.max" => Self::write_max,
+            "io.weight" => Self::write_weight,
+            _ => return Err(Error::AttributeError),
+        };
+
+        let (content, len) = reader
+            .read_cstring_until_end(MAX_ATTR_SIZE)
+            .map_err(|_| Error::PageFault)?;
+        let content = content.to_str().map_err(|_| Error::InvalidOperation)?;
+        for line in content.lines().filter(|line| !line.trim().is_empty()) {
+            write_line(self, line)?;
+        }
+
+        Ok(len)
+    }
+}
+
+impl SubControlStatic for IoController {
+    fn new(_is_root: bool) -> Self {
+        Self {
+            devices: SpinLock::new(BTreeMap::new()),
+            default_weight: AtomicU16::new(DEFAULT_WEIGHT),
+        }
+    }
+
+    fn type_() -> super::SubCtrlType {
+        super::SubCtrlType::Io
+    }
+
+    fn read_from(controller: &super::Controller) -> Arc<super::SubController<Self>> {
+        controller.io.read().get().clone()
+    }
+}
+
+/// Hierarchical I/O charge operations.
+impl super::SubController<IoController> {
+    /// Charges a bio across the hierarchy.
+    ///
+    /// Returns how long the bio should wait to meet the limits at all levels.
+    fn charge_hierarchy(
+        &self,
+        device_id: DeviceId,
+        direction: IoDirection,
+        nr_bytes: u64,
+        now: Duration,
+    ) -> Duration {
+        let mut wait_time = Duration::ZERO;
+
+        let mut current = Some(self);
+        while let Some(node) = current {
+            if let Some(ref inner) = node.inner {
+                let level_wait_time = inner.charge(device_id, direction, nr_bytes, now);
+                wait_time = wait_time.max(level_wait_time);
+            }
+            current = node.parent.as_deref();
+        }
+
+        wait_time
+    }
+}
+
+/// Registers the hook to account and throttle the bios.
+pub(super) fn init() {
+    aster_block::bio::register_submit_hook(on_bio_submit);
+}
+
+fn on_bio_submit(block_device: &dyn BlockDevice, bio: &Bio) {
+    let sid_range = bio.sid_range();
+    let nr_sectors = sid_range.end.to_raw() - sid_range.start.to_raw();
+
+    // The block layer accepts only one submission hook, so the tracepoint is placed here.
+    let rwbs = match bio.type_() {
+        BioType::Read => 'R',
+        BioType::Write => 'W',
+        BioType::Flush => 'F',
+        BioType::Discard => 'D',
+    };
+    let raw_device_id = block_device.id();
+    trace_block_bio_queue(
+        raw_device_id.major().get(),
+        raw_device_id.minor().get(),
+        rwbs,
+        sid_range.start.to_raw(),
+        nr_sectors,
+    );
+
+    let Some(direction) = IoDirection::from_bio_type(bio.type_()) else {
+        return;
+    };
+
+    // Like Linux, the I/O of the partitions is accounted to their disks.
+    let device_id = match block_device.downcast_ref::<PartitionNode>() {
+        Some(partition) => partition.disk().id(),
+        None => raw_device_id,
+    };
+    let nr_bytes = nr_sectors * SECTOR_SIZE as u64;
+
+    let process = Process::current();
+    let sub_controller = match process.as_ref() {
+        Some(process) => {
+            let cgroup = process.cgroup();
+            match cgroup.get() {
+                Some(cgroup) => IoController::read_from(cgroup.controller()),
+                None => IoController::read_from(CgroupSystem::singleton().controller()),
+            }
+        }
+        None => IoController::read_from(CgroupSystem::singleton().controller()),
+    };
+
+    let now = MonotonicClock::get().read_time();
+    let wait_time = sub_controller.charge_hierarchy(device_id, direction, nr_bytes, now);
+    if process.is_none() {
+        return;
+    }
+
+    if !wait_time.is_zero() {
+        let wait_queue = WaitQueue::new();
+        let _ = wait_queue.wait_until_or_timeout(|| -> Option<()> { None }, &wait_time);
+    }
+
+    if direction != IoDirection::Discard
+        && let Some(weighted) = nearest_weighted(&sub_controller)
+    {
+        let weight = weighted.inner.as_ref().unwrap().weight(device_id);
+        Fairness::of(device_id).wait_for_share(&weighted, weight, nr_bytes);
+    }
+}
+
+/// Returns the nearest non-root ancestor-or-self whose I/O sub-controller is active.
+fn nearest_weighted(
+    sub_controller: &Arc<super::SubController<IoController>>,
+) -> Option<Arc<super::SubController<IoController>>> {
+    let mut current = sub_controller;
+    loop {
+        let parent = current.parent.as_ref()?;
+        if current.inner.is_some() {
+            return Some(current.clone());
+        }
+        current = parent;
+    }
+}
+
+/// The weighted sharing of a block device among the cgroups doing I/O on it.
+///
+/// Each cgroup has a virtual time, which advances by the bytes of its I/O divided by its weight.
+/// A cgroup that goes too far ahead of the others waits until they catch up, or until they are
+/// found idle.
+struct Fairness {
+    active_cgroups: SpinLock<Vec<ActiveCgroup>>,
+    wait_queue: WaitQueue,
+}
+
+struct ActiveCgroup {
+    sub_controller: Weak<super::SubController<IoController>>,
+    vtime: u64,
+    last_active: Duration,
+}
+
+static FAIRNESSES: SpinLock<BTreeMap<u32, Arc<Fairness>>> = SpinLock::new(BTreeMap::new());
+
+impl Fairness {
+    fn of(device_id: DeviceId) -> Arc<Self> {
+        FAIRNESSES
+            .lock()
+            .entry(device_id.to_raw())
+            .or_insert_with(|| {
+                Arc::new(Self {
+                    active_cgroups: SpinLock::new(Vec::new()),
+                    wait_queue: WaitQueue::new(),
+                })
+            })
+            .clone()
+    }
+
+    /// Advances the virtual time of the cgroup and waits until it is within its share.
+    fn wait_for_share(
+        &self,
+        sub_controller: &Arc<super::SubController<IoController>>,
+        weight: u16,
+        nr_bytes: u64,
+    ) {
+        let now = MonotonicClock::get().read_time();
+        let vtime = {
+            let mut active_cgroups = self.active_cgroups.lock();
+            active_cgroups.retain(|cgroup| {
+                cgroup.sub_controller.strong_count() > 0
+                    && now.saturating_sub(cgroup.last_active) < ACTIVE_PERIOD
+            });
+
+            let cost = nr_bytes * DEFAULT_WEIGHT as u64 / weight as u64;
+            let min_vtime = active_cgroups.iter().map(|cgroup| cgroup.vtime).min();
+            let weak_sub_controller = Arc::downgrade(sub_controller);
+            let index = match active_cgroups
+                .iter()
+                .position(|cgroup| cgroup.sub_controller.ptr_eq(&weak_sub_controller))
+            {
+                Some(index) => index,
+                None => {
+                    // A newly active cgroup cannot use the share when it was idle.
+                    active_cgroups.push(ActiveCgroup {
+                        sub_controller: weak_sub_controller,
+                        vtime: min_vtime.unwrap_or(0),
+                        last_active: now,
+                    });
+                    active_cgroups.len() - 1
+                }
+            };
+
+            let cgroup = &mut active_cgroups[index];
+            cgroup.vtime += cost;
+            cgroup.last_active = now;
+            cgroup.vtime
+        };
+        self.wait_queue.wake_all();
+
+        loop {
+            let Some(others_vtime) = self.min_vtime_of_others(sub_controller) else {
+                return;
+            };
+            if vtime <= others_vtime + MAX_VTIME_LEAD {
+                return;
+            }
+
+            let has_progress = || {
+                let others_vtime_now = self.min_vtime_of_others(sub_controller);
+                (others_vtime_now != Some(others_vtime)).then_some(())
+            };
+            if self
+                .wait_queue
+                .wait_until_or_timeout(has_progress, &IDLE_TIMEOUT)
+                .is_err()
+            {
+                // The lagging cgroups are idle, so they lose the share that they do not use.
+                self.forward_others(sub_controller, vtime - MAX_VTIME_LEAD);
+                return;
+            }
+        }
+    }
+
+    fn min_vtime_of_others(
+        &self,
+        sub_controller: &Arc<super::SubController<IoController>>,
+    ) -> Option<u64> {
+        let now = MonotonicClock::get().read_time();
+        self.active_cgroups
+            .lock()
+            .iter()
+            .filter(|cgroup| {
+                !core::ptr::eq(cgroup.sub_controller.as_ptr(), Arc::as_ptr(sub_controller))
+                    && now.saturating_sub(cgroup.last_active) < ACTIVE_PERIOD
+            })
+            .map(|cgroup| cgroup.vtime)
+            .min()
+    }
+
+    fn forward_others(&self, sub_controller: &Arc<super::SubController<IoController>>, vtime: u64) {
+        for cgroup in self.active_cgroups.lock().iter_mut() {
+            if !core::ptr::eq(cgroup.sub_controller.as_ptr(), Arc::as_ptr(sub_controller)) {
+                cgroup.vtime = cgroup.vtime.max(vtime);
+            }
+        }
+    }
+}
+
+fn parse_device_id(device: &str) -> Result<DeviceId> {
+    let (major, minor) = device.split_once(':').ok_or(Error::InvalidOperation)?;
+    let major = major
+        .parse::<u16>()
+        .ok()
+        .and_then(|major| MajorId::try_from(major).ok())
+        .ok_or(Error::InvalidOperation)?;
+    let minor = minor
+        .parse::<u32>()
+        .ok()
+        .and_then(|minor| MinorId::try_from(minor).ok())
+        .ok_or(Error::InvalidOperation)?;
+    let device_id = DeviceId::new(major, minor);
+
+    // Like Linux, only the disks can be configured, and the partitions cannot.
+    match aster_block::lookup(device_id) {
+        Some(device) if !device.is_partition() => Ok(device_id),
+        _ => Err(Error::NotFound),
+    }
+}
+
+fn parse_weight(weight: &str) -> Result<u16> {
+    match weight.parse::<u16>() {
+        Ok(weight) if WEIGHT_RANGE.contains(&weight) => Ok(weight),
+        _ => Err(Error::InvalidOperation),
+    }
+}
+
+struct DisplayDeviceId(DeviceId);
+
+impl core::fmt::Display for DisplayDeviceId {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}:{}", self.0.major().get(), self.0.minor().get())
+    }
+}
+
+struct DisplayLimit(u64);
+
+impl core::fmt::Display for DisplayLimit {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if self.0 == u64::MAX {
+            write!(f, "max")
+        } else {
+            write!(f, "{}", self.0)
+        }
+    }
+}
@@ -17,14 +17,23 @@ use ostd::{
 
 use crate::fs::cgroupfs::{
     CgroupMembership, CgroupNode,
-    controller::{cpuset::CpuSetController, memory::MemoryController, pids::PidsController},
+    controller::{
+        cpuset::CpuSetController, io::IoController, memory::MemoryController,
+        pids::PidsController,
+    },
     systree_node::CgroupSysNode,
 };
 
 mod cpuset;
+mod io;
 mod memory;
 mod pids;
 
+/// Initializes the sub-controllers that hook into other subsystems.
+pub(super) fn init() {
+    io::init();
+}
+
 /// A trait to abstract all individual cgroup sub-controllers.
 trait SubControl {
     fn read_attr_at(&self, name: &str, offset: usize, writer: &mut VmWriter) -> Result<usize>;
@@ -49,11 +58,12 @@ trait SubControlStatic: SubControl + Sized + 'static {
 pub(super) enum SubCtrlType {
     Memory,
     CpuSet,
+    Io,
     Pids,
 }
 
 impl SubCtrlType {
-    const ALL: [Self; 3] = [Self::Memory, Self::CpuSet, Self::Pids];
+    const ALL: [Self; 4] = [Self::Memory, Self::CpuSet, Self::Io, Self::Pids];
 }
 
 impl FromStr for SubCtrlType {
@@ -63,6 +73,7 @@ impl FromStr for SubCtrlType {
         match s {
             "memory" => Ok(SubCtrlType::Memory),
             "cpuset" => Ok(SubCtrlType::CpuSet),
+            "io" => Ok(SubCtrlType::Io),
             "pids" => Ok(SubCtrlType::Pids),
             _ => Err(Error::NotFound),
         }
@@ -75,6 +86,7 @@ bitflags! {
         const MEMORY = 1 << 0;
         const CPUSET = 1 << 1;
         const PIDS = 1 << 2;
+        const IO = 1 << 3;
     }
 }
 
@@ -110,6 +122,9 @@ impl Display for SubCtrlSet {
         if self.contains(Self::CPUSET) {
             write!(f, "cpuset ")?;
         }
+        if self.contains(Self::IO) {
+            write!(f, "io ")?;
+        }
         if self.contains(Self::PIDS) {
             write!(f, "pids")?;
         }
@@ -123,6 +138,7 @@ impl From<SubCtrlType> for SubCtrlSet {
         match ctrl_type {
             SubCtrlType::Memory => Self::MEMORY,
             SubCtrlType::CpuSet => Self::CPUSET,
+            SubCtrlType::Io => Self::IO,
             SubCtrlType::Pids => Self::PIDS,
         }
     }
@@ -208,6 +224,7 @@ pub struct Controller {
 
     memory: Rcu<Arc<SubController<MemoryController>>>,
     cpuset: Rcu<Arc<SubController<CpuSetController>>>,
+    io: Rcu<Arc<SubController<IoController>>>,
     pids: Rcu<Arc<SubController<PidsController>>>,
 }
 
@@ -216,12 +233,14 @@ impl Controller {
     pub(super) fn new(parent_controller: Option<&Controller>) -> Self {
         let memory_controller = Arc::new(SubController::new(parent_controller));
         let cpuset_controller = Arc::new(SubController::new(parent_controller));
+        let io_controller = Arc::new(SubController::new(parent_controller));
         let pids_controller = Arc::new(SubController::new(parent_controller));
 
         Self {
             active_set: AtomicSubCtrlSet::new(SubCtrlSet::empty()),
             memory: Rcu::new(memory_controller),
             cpuset: Rcu::new(cpuset_controller),
+            io: Rcu::new(io_controller),
             pids: Rcu::new(pids_controller),
         }
     }
@@ -229,6 +248,7 @@ impl Controller {
     pub(super) fn init_attr_set(builder: &mut SysAttrSetBuilder, is_root: bool) {
         MemoryController::init_attr_set(builder, is_root);
         CpuSetController::init_attr_set(builder, is_root);
+        IoController::init_attr_set(builder, is_root);
         PidsController::init_attr_set(builder, is_root);
     }
 
@@ -236,6 +256,7 @@ impl Controller {
         match ctrl_type {
             SubCtrlType::Memory => MemoryController::read_from(self),
             SubCtrlType::CpuSet => CpuSetController::read_from(self),
+            SubCtrlType::Io => IoController::read_from(self),
             SubCtrlType::Pids => PidsController::read_from(self),
         }
     }
@@ -375,6 +396,10 @@ impl Controller {
                     let new_controller = Arc::new(SubController::new(Some(parent_controller)));
                     child_node.controller().cpuset.update(new_controller);
                 }
+                SubCtrlType::Io => {
+                    let new_controller = Arc::new(SubController::new(Some(parent_controller)));
+                    child_node.controller().io.update(new_controller);
+                }
                 SubCtrlType::Pids => {
                     let mut new_controller: SubController<PidsController> =
                         SubController::new(Some(parent_controller));
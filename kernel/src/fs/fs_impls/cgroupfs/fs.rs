@@ -95,6 +95,7 @@ impl FsType for CgroupFsType {
     fn create(
         &self,
         _flags: FsFlags,
+        _source: Option<&str>,
         _args: Option<CString>,
         _disk: Option<Arc<dyn BlockDevice>>,
     ) -> Result<Arc<dyn FileSystem>> {
@@ -12,4 +12,5 @@ mod systree_node;
 // _after_ `aster_systree::init`.
 pub(super) fn init() {
     crate::fs::vfs::registry::register(&CgroupFsType).unwrap();
+    controller::init();
 }
@@ -100,6 +100,7 @@ impl FsType for ConfigFsType {
     fn create(
         &self,
         _flags: FsFlags,
+        _source: Option<&str>,
         _args: Option<CString>,
         _disk: Option<Arc<dyn BlockDevice>>,
     ) -> Result<Arc<dyn FileSystem>> {
@@ -0,0 +1,112 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::sync::Arc;
+
+use aster_block::BlockDevice;
+use aster_systree::SysNode;
+use spin::Once;
+
+use super::inode::DebugInode;
+use crate::{
+    fs::{
+        Result,
+        debugfs::systree_node::DebugRootNode,
+        pseudofs::AnonDeviceId,
+        utils::systree_inode::SysTreeInodeTy,
+        vfs::{
+            file_system::{FileSystem, FsEventSubscriberStats, FsFlags, SuperBlock},
+            inode::Inode,
+            registry::{FsProperties, FsType},
+        },
+    },
+    prelude::*,
+};
+
+/// A file system that exports kernel-internal states for debugging.
+///
+/// `DebugFs` is a RAM-based file system where kernel subsystems can place files
+/// to expose their internal states. Unlike procfs and sysfs, debugfs has no stable
+/// ABI, so the files in it can be added, changed, or removed freely.
+pub struct DebugFs {
+    _anon_device_id: AnonDeviceId,
+    sb: SuperBlock,
+    root: Arc<dyn Inode>,
+    fs_event_subscriber_stats: FsEventSubscriberStats,
+}
+
+// Magic number for `DebugFs` (taken from Linux).
+const MAGIC_NUMBER: u64 = 0x64626720;
+const BLOCK_SIZE: usize = 4096;
+const NAME_MAX: usize = 255;
+
+impl DebugFs {
+    /// Returns the `DebugFs` singleton.
+    pub(super) fn singleton() -> &'static Arc<DebugFs> {
+        static SINGLETON: Once<Arc<DebugFs>> = Once::new();
+
+        SINGLETON.call_once(|| Self::new(DebugRootNode::singleton().clone()))
+    }
+
+    fn new(root_node: Arc<DebugRootNode>) -> Arc<Self> {
+        let anon_device_id =
+            AnonDeviceId::acquire().expect("no device ID is available for debugfs");
+        let sb = SuperBlock::new(MAGIC_NUMBER, BLOCK_SIZE, NAME_MAX, anon_device_id.id());
+        let root_inode = DebugInode::new_root(root_node, &sb);
+
+        Arc::new(Self {
+            _anon_device_id: anon_device_id,
+            sb,
+            root: root_inode,
+            fs_event_subscriber_stats: FsEventSubscriberStats::new(),
+        })
+    }
+}
+
+impl FileSystem for DebugFs {
+    fn name(&self) -> &'static str {
+        "debugfs"
+    }
+
+    fn sync(&self) -> Result<()> {
+        // `DebugFs` is volatile, sync is a no-op
+        Ok(())
+    }
+
+    fn root_inode(&self) -> Arc<dyn Inode> {
+        self.root.clone()
+    }
+
+    fn sb(&self) -> SuperBlock {
+        self.sb.clone()
+    }
+
+    fn fs_event_subscriber_stats(&self) -> &FsEventSubscriberStats {
+        &self.fs_event_subscriber_stats
+    }
+}
+
+pub(super) struct DebugFsType;
+
+impl FsType for DebugFsType {
+    fn name(&self) -> &'static str {
+        "debugfs"
+    }
+
+    fn properties(&self) -> FsProperties {
+        FsProperties::empty()
+    }
+
+    fn create(
+        &self,
+        _flags: FsFlags,
+        _source: Option<&str>,
+        _args: Option<CString>,
+        _disk: Option<Arc<dyn BlockDevice>>,
+    ) -> Result<Arc<dyn FileSystem>> {
+        Ok(DebugFs::singleton().clone() as _)
+    }
+
+    fn sysnode(&self) -> Option<Arc<dyn SysNode>> {
+        None
+    }
+}
@@ -0,0 +1,50 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Debug file system (debugfs).
+//!
+//! Debugfs is a place for kernel subsystems to export their internal states for debugging.
+//! A subsystem registers a [`DebugDir`] under the root of debugfs, whose files are
+//! [`DebugFile`]s backed by closures.
+//!
+//! Debugfs is usually mounted at `/sys/kernel/debug`.
+
+use alloc::sync::Arc;
+
+use aster_systree::{EmptyNode, SysBranchNode};
+use systree_node::DebugRootNode;
+pub use systree_node::{DebugDir, DebugFile};
+
+use crate::{fs::debugfs::fs::DebugFsType, prelude::*};
+
+mod fs;
+mod inode;
+mod systree_node;
+
+// This method should be called during kernel file system initialization,
+// _after_ `aster_systree::init`.
+pub(super) fn init() {
+    let debug_kernel_sysnode = EmptyNode::new("debug".into());
+    super::sysfs::register_kernel_sysnode(debug_kernel_sysnode).unwrap();
+
+    crate::fs::vfs::registry::register(&DebugFsType).unwrap();
+}
+
+/// Registers a top-level directory under the root of debugfs.
+///
+/// If a directory with the same name has already been registered,
+/// this function returns an error.
+pub fn register_dir(dir: Arc<DebugDir>) -> Result<()> {
+    DebugRootNode::singleton().add_child(dir)?;
+
+    Ok(())
+}
+
+/// Unregisters a top-level directory from the root of debugfs by its name.
+///
+/// If no directory with the given name exists, this function returns an error.
+#[expect(dead_code)]
+pub fn unregister_dir(name: &str) -> Result<()> {
+    DebugRootNode::singleton().remove_child(name)?;
+
+    Ok(())
+}
@@ -136,6 +136,7 @@ impl FsType for DevPtsType {
     fn create(
         &self,
         _flags: FsFlags,
+        _source: Option<&str>,
         _args: Option<CString>,
         _disk: Option<Arc<dyn aster_block::BlockDevice>>,
     ) -> Result<Arc<dyn FileSystem>> {
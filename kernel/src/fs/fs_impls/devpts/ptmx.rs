@@ -179,6 +179,10 @@ impl Device for Inner {
         None
     }
 
+    fn class(&self) -> &'static str {
+        "tty"
+    }
+
     fn open(&self) -> Result<Box<dyn FileIo>> {
         let devpts = self.0.upgrade().unwrap();
         Ok(devpts.create_master_slave_pair()?.0)
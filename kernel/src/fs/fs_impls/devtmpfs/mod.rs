@@ -0,0 +1,278 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Device temporary file system (devtmpfs).
+//!
+//! Devtmpfs is a tmpfs whose device nodes are maintained by the kernel. When a device is
+//! registered, its device node is created in devtmpfs. When the device is unregistered,
+//! its device node is removed.
+//!
+//! There is only one devtmpfs instance, which is mounted at `/dev` by the kernel.
+
+use spin::Once;
+
+use super::tmpfs::TmpFs;
+use crate::{
+    fs::{
+        file::{InodeMode, InodeType, mkmod},
+        vfs::{
+            file_system::{FileSystem, FsEventSubscriberStats, FsFlags, SuperBlock},
+            inode::{Inode, MknodType},
+            path::{Mount, Path, PerMountFlags},
+            registry::{FsProperties, FsType},
+        },
+    },
+    prelude::*,
+};
+
+/// The device temporary file system (devtmpfs).
+pub struct DevTmpFs {
+    inner: Arc<TmpFs>,
+    /// The root of the mount through which the kernel maintains the device nodes.
+    ///
+    /// Initially, this is the root of an internal mount. Once devtmpfs is mounted at `/dev`,
+    /// this becomes the root of that mount, so that the dentries of removed device nodes will
+    /// not remain in the dentry cache of that mount.
+    //
+    // TODO: If devtmpfs is mounted elsewhere by the user space, the dentries of removed device
+    // nodes may remain in the dentry cache of the other mounts.
+    node_root: Mutex<Path>,
+}
+
+impl DevTmpFs {
+    /// Returns the `DevTmpFs` singleton.
+    fn singleton() -> &'static Arc<DevTmpFs> {
+        static SINGLETON: Once<Arc<DevTmpFs>> = Once::new();
+
+        SINGLETON.call_once(Self::new)
+    }
+
+    fn new() -> Arc<Self> {
+        let inner = TmpFs::new();
+        let node_root = Path::new_fs_root(Mount::new_pseudo(inner.clone()));
+
+        for (name, target) in STANDARD_SYMLINKS {
+            let symlink = node_root
+                .new_fs_child(name, InodeType::SymLink, mkmod!(a+rwx))
+                .unwrap();
+            symlink.inode().write_link(target).unwrap();
+        }
+
+        Arc::new(Self {
+            inner,
+            node_root: Mutex::new(node_root),
+        })
+    }
+}
+
+/// The symlinks that are always present in devtmpfs.
+///
+/// Reference: <https://github.com/systemd/systemd/blob/v257/src/shared/dev-setup.c#L27>
+const STANDARD_SYMLINKS: [(&str, &str); 4] = [
+    ("fd", "/proc/self/fd"),
+    ("stdin", "/proc/self/fd/0"),
+    ("stdout", "/proc/self/fd/1"),
+    ("stderr", "/proc/self/fd/2"),
+];
+
+impl FileSystem for DevTmpFs {
+    fn name(&self) -> &'static str {
+        "devtmpfs"
+    }
+
+    fn sync(&self) -> Result<()> {
+        // do nothing
+        Ok(())
+    }
+
+    fn root_inode(&self) -> Arc<dyn Inode> {
+        self.inner.root_inode()
+    }
+
+    fn sb(&self) -> SuperBlock {
+        self.inner.sb()
+    }
+
+    fn fs_event_subscriber_stats(&self) -> &FsEventSubscriberStats {
+        self.inner.fs_event_subscriber_stats()
+    }
+}
+
+struct DevTmpFsType;
+
+impl FsType for DevTmpFsType {
+    fn name(&self) -> &'static str {
+        "devtmpfs"
+    }
+
+    fn properties(&self) -> FsProperties {
+        FsProperties::empty()
+    }
+
+    fn create(
+        &self,
+        _flags: FsFlags,
+        _source: Option<&str>,
+        _args: Option<CString>,
+        _disk: Option<Arc<dyn aster_block::BlockDevice>>,
+    ) -> Result<Arc<dyn FileSystem>> {
+        Ok(DevTmpFs::singleton().clone() as _)
+    }
+
+    fn sysnode(&self) -> Option<Arc<dyn aster_systree::SysNode>> {
+        None
+    }
+}
+
+pub(super) fn init() {
+    crate::fs::vfs::registry::register(&DevTmpFsType).unwrap();
+
+    // Create the singleton so that the device nodes can be added once devices are registered.
+    DevTmpFs::singleton();
+}
+
+/// Mounts devtmpfs at `dev_path`, which is usually `/dev`.
+///
+/// This should be called only once by the kernel.
+pub fn mount_at(dev_path: &Path, ctx: &Context) -> Result<()> {
+    let devtmpfs = DevTmpFs::singleton();
+
+    let mut node_root = devtmpfs.node_root.lock();
+    let dev_mount = dev_path.mount(
+        devtmpfs.clone(),
+        PerMountFlags::default(),
+        Some("devtmpfs".to_string()),
+        ctx,
+    )?;
+    *node_root = Path::new_fs_root(dev_mount);
+
+    Ok(())
+}
+
+/// Adds a device node at `path` relative to the root of devtmpfs.
+///
+/// If the parent directories do not exist, they will be created.
+pub fn add_node(path: &str, mode: InodeMode, mknod_type: MknodType) -> Result<()> {
+    let (dir_names, name) = split_node_path(path)?;
+
+    let node_root = DevTmpFs::singleton().node_root.lock();
+
+    let dir = lookup_or_create_dirs(&node_root, &dir_names)?;
+    dir.mknod(name, mode, mknod_type)?;
+
+    Ok(())
+}
+
+/// Removes the device node at `path` relative to the root of devtmpfs.
+///
+/// The node is removed only if it is still the device node of `mknod_type`, since it
+/// may have been replaced by the user space. The parent directories that become empty
+/// are also removed.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.16/source/drivers/base/devtmpfs.c#L280>
+pub fn remove_node(path: &str, mknod_type: MknodType) -> Result<()> {
+    remove_entry(path, |node| {
+        if !is_device_node(node, &mknod_type) {
+            return_errno_with_message!(Errno::ENOENT, "the device node has been replaced");
+        }
+        Ok(())
+    })
+}
+
+/// Adds a symlink at `path` relative to the root of devtmpfs, which points to `target`.
+///
+/// Devtmpfs only contains device nodes in Linux, and the symlinks to them (e.g.,
+/// `/dev/virtio-ports/<name>`) are added by udev rules. Since we do not have udev, the kernel
+/// adds such symlinks instead.
+///
+/// If the parent directories do not exist, they will be created.
+pub fn add_symlink(path: &str, target: &str) -> Result<()> {
+    let (dir_names, name) = split_node_path(path)?;
+
+    let node_root = DevTmpFs::singleton().node_root.lock();
+
+    let dir = lookup_or_create_dirs(&node_root, &dir_names)?;
+    let symlink = dir.new_fs_child(name, InodeType::SymLink, mkmod!(a+rwx))?;
+    symlink.inode().write_link(target)?;
+
+    Ok(())
+}
+
+/// Removes the symlink at `path` relative to the root of devtmpfs.
+///
+/// The parent directories that become empty are also removed.
+pub fn remove_symlink(path: &str) -> Result<()> {
+    remove_entry(path, |node| {
+        if node.metadata().type_ != InodeType::SymLink {
+            return_errno_with_message!(Errno::ENOENT, "the symlink has been replaced");
+        }
+        Ok(())
+    })
+}
+
+/// Looks up the directories at `dir_names` from `node_root`, creating them if they do not exist.
+fn lookup_or_create_dirs(node_root: &Path, dir_names: &[&str]) -> Result<Path> {
+    let mut dir = node_root.clone();
+    for dir_name in dir_names {
+        dir = match dir.child_within_mount(dir_name) {
+            Ok(child) => child,
+            Err(err) if err.error() == Errno::ENOENT => {
+                dir.new_fs_child(dir_name, InodeType::Dir, mkmod!(a+rx, u+w))?
+            }
+            Err(err) => return Err(err),
+        };
+    }
+
+    Ok(dir)
+}
+
+/// Removes the entry at `path` relative to the root of devtmpfs if `check_node` succeeds, and
+/// then removes the parent directories that become empty.
+fn remove_entry(path: &str, check_node: impl FnOnce(&Path) -> Result<()>) -> Result<()> {
+    let (dir_names, name) = split_node_path(path)?;
+
+    let node_root = DevTmpFs::singleton().node_root.lock();
+
+    let mut dirs = vec![node_root.clone()];
+    for dir_name in dir_names.iter() {
+        let dir = dirs.last().unwrap().child_within_mount(dir_name)?;
+        dirs.push(dir);
+    }
+
+    let parent_dir = dirs.last().unwrap();
+    let node = parent_dir.child_within_mount(name)?;
+    check_node(&node)?;
+    parent_dir.unlink(name)?;
+
+    // Remove the empty parent directories, from the innermost one to the outermost one.
+    for (dir, dir_name) in dirs.iter().rev().skip(1).zip(dir_names.iter().rev()) {
+        if dir.rmdir(dir_name).is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Splits the path of a device node into the names of the parent directories and its name.
+fn split_node_path(path: &str) -> Result<(Vec<&str>, &str)> {
+    let mut names: Vec<&str> = path.split('/').filter(|name| !name.is_empty()).collect();
+    let Some(name) = names.pop() else {
+        return_errno_with_message!(Errno::EINVAL, "the device path is invalid");
+    };
+
+    Ok((names, name))
+}
+
+fn is_device_node(node: &Path, mknod_type: &MknodType) -> bool {
+    let metadata = node.metadata();
+    let (expected_type, expected_id) = match mknod_type {
+        MknodType::CharDevice(dev_id) => (InodeType::CharDevice, *dev_id),
+        MknodType::BlockDevice(dev_id) => (InodeType::BlockDevice, *dev_id),
+        MknodType::NamedPipe => return false,
+    };
+
+    metadata.type_ == expected_type
+        && metadata
+            .self_dev_id
+            .is_some_and(|dev_id| dev_id.as_encoded_u64() == expected_id)
+}
@@ -481,6 +481,7 @@ impl FsType for ExfatType {
     fn create(
         &self,
         _flags: FsFlags,
+        _source: Option<&str>,
         _args: Option<CString>,
         disk: Option<Arc<dyn BlockDevice>>,
     ) -> Result<Arc<dyn FileSystem>> {
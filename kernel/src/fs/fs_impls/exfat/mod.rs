@@ -109,6 +109,8 @@ mod test {
             BlockDeviceMeta {
                 max_nr_segments_per_bio: usize::MAX,
                 nr_sectors: self.sectors_count(),
+                supports_fua: false,
+                max_discard_sectors: 0,
             }
         }
 
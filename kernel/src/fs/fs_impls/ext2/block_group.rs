@@ -4,10 +4,12 @@ use ostd::{const_assert, mm::io::util::HasVmReaderWriter};
 
 use super::{
     block_ptr::Ext2Bid,
+    checksum::{crc16, crc32c},
+    extent::ExtentTree,
     fs::Ext2,
-    inode::{Inode, InodeDesc, RawInode},
+    inode::{FileFlags, Inode, InodeDesc, RawInode},
     prelude::*,
-    super_block::SuperBlock,
+    super_block::{FeatureInCompatSet, MetadataChecksum, SuperBlock},
 };
 use crate::fs::utils::IdBitmap;
 
@@ -36,26 +38,63 @@ impl BlockGroup {
         fs: Weak<Ext2>,
     ) -> Result<Self> {
         let raw_inodes_size = (super_block.inodes_per_group() as usize) * super_block.inode_size();
+        let metadata_checksum = super_block.metadata_checksum();
+        let desc_size = super_block.desc_size();
 
         let bg_impl = {
             let metadata = {
                 let descriptor = {
                     // Read the block group descriptor
                     // TODO: if the main is corrupted, should we load the backup?
-                    let offset = idx * size_of::<RawGroupDescriptor>();
-                    let raw_descriptor = group_descriptors_segment
-                        .read_val::<RawGroupDescriptor>(offset)
+                    let offset = idx * desc_size;
+                    let mut raw_descriptor = RawGroupDescriptor::new_zeroed();
+                    group_descriptors_segment
+                        .read_bytes(offset, &mut raw_descriptor.as_mut_bytes()[..desc_size])
                         .unwrap();
-                    GroupDescriptor::from(raw_descriptor)
+                    let checksum =
+                        raw_descriptor.compute_checksum(idx, desc_size, metadata_checksum);
+                    if checksum.is_some_and(|checksum| checksum != raw_descriptor.checksum) {
+                        return_errno_with_message!(
+                            Errno::EBADMSG,
+                            "bad block group descriptor checksum"
+                        );
+                    }
+
+                    let mut descriptor = GroupDescriptor::from(raw_descriptor);
+                    // The flags are valid only if the group descriptors have checksums.
+                    if checksum.is_none() {
+                        descriptor.flags = GroupFlags::empty();
+                    }
+                    descriptor
                 };
 
-                let get_bitmap = |bid: Ext2Bid, capacity: usize| -> Result<IdBitmap> {
+                // The checksum always covers the bits of a full block group.
+                let get_bitmap = |bid: Ext2Bid,
+                                  capacity: usize,
+                                  csum_bits: usize,
+                                  checksum: u32,
+                                  is_uninit: bool|
+                 -> Result<Vec<u8>> {
                     if capacity > BLOCK_SIZE * 8 {
                         return_errno_with_message!(Errno::EINVAL, "bad bitmap");
                     }
                     let mut buf = vec![0u8; BLOCK_SIZE];
+                    if is_uninit {
+                        // Like Linux, the bits beyond the capacity are set.
+                        for bit in capacity..BLOCK_SIZE * 8 {
+                            set_bit(&mut buf, bit);
+                        }
+                        return Ok(buf);
+                    }
+
                     block_device.read_bytes(bid as usize * BLOCK_SIZE, &mut buf)?;
-                    Ok(IdBitmap::from_buf(buf.into_boxed_slice(), capacity as u16))
+                    if let MetadataChecksum::Crc32c { seed } = metadata_checksum {
+                        let expected = bitmap_checksum(&buf, csum_bits, seed);
+                        if !checksum_matches(checksum, expected, desc_size) {
+                            return_errno_with_message!(Errno::EBADMSG, "bad bitmap checksum");
+                        }
+                    }
+                    Ok(buf)
                 };
 
                 let block_bitmap = {
@@ -65,12 +104,30 @@ impl BlockGroup {
                         // The last block group may have less blocks than others.
                         super_block.total_blocks() - super_block.blocks_per_group() * idx as u32
                     };
-                    get_bitmap(descriptor.block_bitmap_bid, num_blocks as usize)?
+                    let is_uninit = descriptor.flags.contains(GroupFlags::BLOCK_UNINIT);
+                    let mut buf = get_bitmap(
+                        descriptor.block_bitmap_bid,
+                        num_blocks as usize,
+                        super_block.blocks_per_group() as usize,
+                        descriptor.block_bitmap_csum,
+                        is_uninit,
+                    )?;
+                    if is_uninit {
+                        init_block_bitmap(&mut buf, num_blocks, idx, &descriptor, super_block)?;
+                    }
+                    IdBitmap::from_buf(buf.into_boxed_slice(), num_blocks as u16)
+                };
+                let inode_bitmap = {
+                    let num_inodes = super_block.inodes_per_group() as usize;
+                    let buf = get_bitmap(
+                        descriptor.inode_bitmap_bid,
+                        num_inodes,
+                        num_inodes,
+                        descriptor.inode_bitmap_csum,
+                        descriptor.flags.contains(GroupFlags::INODE_UNINIT),
+                    )?;
+                    IdBitmap::from_buf(buf.into_boxed_slice(), num_inodes as u16)
                 };
-                let inode_bitmap = get_bitmap(
-                    descriptor.inode_bitmap_bid,
-                    super_block.inodes_per_group() as usize,
-                )?;
 
                 GroupMetadata {
                     descriptor,
@@ -132,17 +189,41 @@ impl BlockGroup {
     /// This method may load the raw inode metadata from block device.
     fn load_inode(&self, inode_idx: u32) -> Result<Arc<Inode>> {
         let fs = self.fs();
+        let ino = inode_idx + self.idx as u32 * fs.inodes_per_group() + 1;
         let raw_inode = {
             let offset = (inode_idx as usize) * fs.inode_size();
+            let mut buf = vec![0u8; fs.inode_size()];
             self.raw_inodes_cache
                 .pages()
-                .read_val::<RawInode>(offset)
-                .unwrap()
+                .read_bytes(offset, &mut buf)
+                .unwrap();
+            if let Some(seed) = fs.csum_seed() {
+                let mut expected = buf.clone();
+                RawInode::update_checksum(&mut expected, seed, ino);
+                if expected != buf {
+                    return_errno_with_message!(Errno::EBADMSG, "bad inode checksum");
+                }
+            }
+            RawInode::from_first_bytes(&buf)
         };
         let inode_desc = Dirty::new(InodeDesc::try_from(raw_inode)?);
-        let ino = inode_idx + self.idx as u32 * fs.inodes_per_group() + 1;
+        let extent_tree = if inode_desc.file_flags().contains(FileFlags::EXTENTS) {
+            let csum_seed = fs.inode_csum_seed(ino, inode_desc.generation());
+            let read_block = |bid: Ext2Bid, buf: &mut [u8]| {
+                fs.read_metadata_bytes(bid as usize * BLOCK_SIZE, buf)
+            };
+            Some(ExtentTree::load(
+                &raw_inode.block_ptrs,
+                &read_block,
+                csum_seed,
+            )?)
+        } else {
+            None
+        };
 
-        Ok(Inode::new(ino, self.idx, inode_desc, Arc::downgrade(&fs)))
+        let inode = Inode::new(ino, self.idx, inode_desc, extent_tree, Arc::downgrade(&fs));
+        inode.load_fscrypt_context()?;
+        Ok(inode)
     }
 
     /// Inserts the inode into the inode cache.
@@ -214,13 +295,88 @@ impl BlockGroup {
         inner.metadata.free_blocks(range);
     }
 
+    /// Discards the free blocks in the `range` of block indices with `discard`, skipping the
+    /// free extents shorter than `min_len` blocks.
+    ///
+    /// The blocks in this group cannot be allocated until they are discarded. Returns the
+    /// number of discarded blocks.
+    pub fn trim_free_blocks(
+        &self,
+        range: Range<Ext2Bid>,
+        min_len: Ext2Bid,
+        mut discard: impl FnMut(Range<Ext2Bid>) -> Result<()>,
+    ) -> Result<Ext2Bid> {
+        // The fast path
+        if self.bg_impl.inner.read().metadata.free_blocks_count() == 0 {
+            return Ok(0);
+        }
+
+        // The slow path
+        let inner = self.bg_impl.inner.write();
+        let metadata = &inner.metadata;
+        let end = range.end.min(metadata.block_bitmap.len() as Ext2Bid);
+        let mut nr_trimmed = 0;
+        let mut idx = range.start;
+        while idx < end {
+            if metadata.is_block_allocated(idx) {
+                idx += 1;
+                continue;
+            }
+
+            let extent_start = idx;
+            while idx < end && !metadata.is_block_allocated(idx) {
+                idx += 1;
+            }
+            if idx - extent_start >= min_len {
+                discard(extent_start..idx)?;
+                nr_trimmed += idx - extent_start;
+            }
+        }
+
+        Ok(nr_trimmed)
+    }
+
     /// Writes back the raw inode metadata to the raw inode metadata cache.
     pub fn sync_raw_inode(&self, inode_idx: u32, raw_inode: &RawInode) {
-        let offset = (inode_idx as usize) * self.fs().inode_size();
+        let fs = self.fs();
+        let offset = (inode_idx as usize) * fs.inode_size();
         self.raw_inodes_cache
             .pages()
             .write_val(offset, raw_inode)
             .unwrap();
+
+        // The checksum covers the extra fields after the raw inode.
+        if let Some(seed) = fs.csum_seed() {
+            let ino = inode_idx + self.idx as u32 * fs.inodes_per_group() + 1;
+            let mut buf = vec![0u8; fs.inode_size()];
+            self.raw_inodes_cache
+                .pages()
+                .read_bytes(offset, &mut buf)
+                .unwrap();
+            RawInode::update_checksum(&mut buf, seed, ino);
+            self.raw_inodes_cache
+                .pages()
+                .write_bytes(offset, &buf)
+                .unwrap();
+        }
+    }
+
+    /// Resets the raw inode metadata for a newly allocated inode.
+    ///
+    /// The slot is zeroed, and the size of the extra fields is set to `extra_isize`.
+    pub fn reset_raw_inode(&self, inode_idx: u32, extra_isize: usize) {
+        let fs = self.fs();
+        let inode_size = fs.inode_size();
+        let mut buf = vec![0u8; inode_size];
+        if inode_size > size_of::<RawInode>() {
+            let extra_isize = extra_isize.min(inode_size - size_of::<RawInode>()) as u16;
+            buf[size_of::<RawInode>()..size_of::<RawInode>() + 2]
+                .copy_from_slice(&extra_isize.to_le_bytes());
+        }
+        self.raw_inodes_cache
+            .pages()
+            .write_bytes((inode_idx as usize) * inode_size, &buf)
+            .unwrap();
     }
 
     /// Writes back the metadata of this group.
@@ -231,6 +387,26 @@ impl BlockGroup {
 
         let mut inner = self.bg_impl.inner.write();
         let fs = self.fs();
+        // The bitmaps are initialized once they are written back.
+        inner
+            .metadata
+            .descriptor
+            .flags
+            .remove(GroupFlags::BLOCK_UNINIT | GroupFlags::INODE_UNINIT);
+        if let Some(seed) = fs.csum_seed() {
+            let metadata = &mut *inner.metadata;
+            metadata.descriptor.block_bitmap_csum = bitmap_checksum(
+                metadata.block_bitmap.as_bytes(),
+                fs.blocks_per_group() as usize,
+                seed,
+            );
+            metadata.descriptor.inode_bitmap_csum = bitmap_checksum(
+                metadata.inode_bitmap.as_bytes(),
+                fs.inodes_per_group() as usize,
+                seed,
+            );
+        }
+
         // Writes back the descriptor.
         let raw_descriptor = RawGroupDescriptor::from(&inner.metadata.descriptor);
         self.fs().sync_group_descriptor(self.idx, &raw_descriptor)?;
@@ -238,14 +414,14 @@ impl BlockGroup {
         let mut bio_waiter = BioWaiter::new();
         // Writes back the inode bitmap.
         let inode_bitmap_bid = Bid::new(inner.metadata.descriptor.inode_bitmap_bid as u64);
-        bio_waiter.concat(fs.block_device().write_bytes_async(
+        bio_waiter.concat(fs.write_metadata_bytes_async(
             inode_bitmap_bid.to_offset(),
             inner.metadata.inode_bitmap.as_bytes(),
         )?);
 
         // Writes back the block bitmap.
         let block_bitmap_bid = Bid::new(inner.metadata.descriptor.block_bitmap_bid as u64);
-        bio_waiter.concat(fs.block_device().write_bytes_async(
+        bio_waiter.concat(fs.write_metadata_bytes_async(
             block_bitmap_bid.to_offset(),
             inner.metadata.block_bitmap.as_bytes(),
         )?);
@@ -326,30 +502,18 @@ impl Debug for BlockGroup {
 impl PageCacheBackend for BlockGroupImpl {
     fn read_page_async(&self, idx: usize, frame: &CachePage) -> Result<BioWaiter> {
         let bid = self.inode_table_bid + idx as Ext2Bid;
-        // TODO: Should we allocate the bio segment from the pool on reads?
-        // This may require an additional copy to the requested frame in the completion callback.
-        let bio_segment = BioSegment::new_from_segment(
-            Segment::from(frame.clone()).into(),
-            BioDirection::FromDevice,
-        );
         self.fs
             .upgrade()
             .unwrap()
-            .read_blocks_async(bid, bio_segment)
+            .read_metadata_blocks_async(bid, Segment::from(frame.clone()).into())
     }
 
     fn write_page_async(&self, idx: usize, frame: &CachePage) -> Result<BioWaiter> {
         let bid = self.inode_table_bid + idx as Ext2Bid;
-        let bio_segment = BioSegment::alloc(1, BioDirection::ToDevice);
-        // This requires an additional copy to the pooled bio segment.
-        bio_segment
-            .writer()
-            .unwrap()
-            .write_fallible(&mut frame.reader().to_fallible())?;
         self.fs
             .upgrade()
             .unwrap()
-            .write_blocks_async(bid, bio_segment)
+            .write_metadata_blocks_async(bid, &Segment::from(frame.clone()).into())
     }
 
     fn npages(&self) -> usize {
@@ -378,6 +542,14 @@ impl GroupMetadata {
     pub fn alloc_inode(&mut self, is_dir: bool) -> Option<u32> {
         let inode_idx = self.inode_bitmap.alloc()?;
         self.dec_free_inodes();
+        // The inodes beyond the used part of the inode table are considered unused.
+        let used_inodes = self
+            .inode_bitmap
+            .len()
+            .saturating_sub(self.descriptor.itable_unused);
+        if inode_idx >= used_inodes {
+            self.descriptor.itable_unused = self.inode_bitmap.len() - inode_idx - 1;
+        }
         if is_dir {
             self.inc_dirs();
         }
@@ -477,6 +649,16 @@ struct GroupDescriptor {
     free_inodes_count: u16,
     /// Number of directories in group
     dirs_count: u16,
+    /// Block group flags
+    flags: GroupFlags,
+    /// Snapshot exclusion bitmap block
+    exclude_bitmap_bid: Ext2Bid,
+    /// Checksum of the block bitmap
+    block_bitmap_csum: u32,
+    /// Checksum of the inode bitmap
+    inode_bitmap_csum: u32,
+    /// Number of unused inodes at the end of the inode table
+    itable_unused: u16,
 }
 
 impl From<RawGroupDescriptor> for GroupDescriptor {
@@ -488,15 +670,111 @@ impl From<RawGroupDescriptor> for GroupDescriptor {
             free_blocks_count: desc.free_blocks_count,
             free_inodes_count: desc.free_inodes_count,
             dirs_count: desc.dirs_count,
+            flags: GroupFlags::from_bits_truncate(desc.flags),
+            exclude_bitmap_bid: desc.exclude_bitmap,
+            block_bitmap_csum: ((desc.block_bitmap_csum_hi as u32) << 16)
+                | desc.block_bitmap_csum as u32,
+            inode_bitmap_csum: ((desc.inode_bitmap_csum_hi as u32) << 16)
+                | desc.inode_bitmap_csum as u32,
+            itable_unused: desc.itable_unused,
+        }
+    }
+}
+
+bitflags! {
+    /// Block group flags.
+    ///
+    /// The flags are valid only if the group descriptors have checksums.
+    struct GroupFlags: u16 {
+        /// The inode table and the inode bitmap are not initialized.
+        const INODE_UNINIT = 1 << 0;
+        /// The block bitmap is not initialized.
+        const BLOCK_UNINIT = 1 << 1;
+        /// The inode table is zeroed.
+        const INODE_ZEROED = 1 << 2;
+    }
+}
+
+/// Initializes the block bitmap of an uninitialized block group.
+///
+/// The blocks occupied by the metadata of the group are marked as allocated.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/fs/ext4/balloc.c>.
+fn init_block_bitmap(
+    buf: &mut [u8],
+    num_blocks: u32,
+    idx: usize,
+    descriptor: &GroupDescriptor,
+    super_block: &SuperBlock,
+) -> Result<()> {
+    if super_block
+        .feature_incompat()
+        .contains(FeatureInCompatSet::META_BG)
+    {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "uninitialized block groups with meta_bg are not supported"
+        );
+    }
+
+    let mut metadata_ranges = Vec::with_capacity(4);
+    let group_start = (idx as Ext2Bid) * super_block.blocks_per_group();
+    if super_block.has_super(idx) {
+        let nblocks = 1 + super_block.gdt_blocks_count() + super_block.reserved_gdt_blocks();
+        metadata_ranges.push(group_start..group_start + nblocks as Ext2Bid);
+    }
+    metadata_ranges.push(descriptor.block_bitmap_bid..descriptor.block_bitmap_bid + 1);
+    metadata_ranges.push(descriptor.inode_bitmap_bid..descriptor.inode_bitmap_bid + 1);
+    let inode_table_blocks = (super_block.inodes_per_group() as usize * super_block.inode_size())
+        .div_ceil(BLOCK_SIZE) as Ext2Bid;
+    metadata_ranges
+        .push(descriptor.inode_table_bid..descriptor.inode_table_bid + inode_table_blocks);
+
+    // With flexible block groups, the metadata may be located in other groups.
+    let group_end = group_start + num_blocks;
+    for range in metadata_ranges {
+        let start = range.start.clamp(group_start, group_end);
+        let end = range.end.clamp(group_start, group_end);
+        for bid in start..end {
+            set_bit(buf, (bid - group_start) as usize);
         }
     }
+    Ok(())
+}
+
+/// Sets the `bit`-th bit in the buffer.
+fn set_bit(buf: &mut [u8], bit: usize) {
+    buf[bit / 8] |= 1 << (bit % 8);
 }
 
-const_assert!(size_of::<RawGroupDescriptor>() == 32);
+/// Computes the checksum of the bitmap with `nbits` bits.
+fn bitmap_checksum(buf: &[u8], nbits: usize, seed: u32) -> u32 {
+    crc32c(seed, &buf[..nbits / 8])
+}
+
+/// Checks if the stored checksum matches the expected one.
+///
+/// The high 16 bits are stored only if the group descriptor is large enough.
+fn checksum_matches(stored: u32, expected: u32, desc_size: usize) -> bool {
+    if desc_size >= BITMAP_CSUM_HI_END {
+        stored == expected
+    } else {
+        stored & 0xffff == expected & 0xffff
+    }
+}
+
+/// The end offset of the high 16 bits of the bitmap checksums in the group descriptor.
+const BITMAP_CSUM_HI_END: usize = 0x3c;
+
+/// The offset of the checksum in the group descriptor.
+const CHECKSUM_OFFSET: usize = 0x1e;
+
+const_assert!(size_of::<RawGroupDescriptor>() == 64);
 
 /// The raw block group descriptor.
 ///
 /// The table starts on the first block following the superblock.
+/// Only the first 32 bytes are stored on the device if the 64-bit feature is disabled.
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Pod)]
 pub(super) struct RawGroupDescriptor {
@@ -506,8 +784,58 @@ pub(super) struct RawGroupDescriptor {
     pub free_blocks_count: u16,
     pub free_inodes_count: u16,
     pub dirs_count: u16,
-    pad: u16,
-    reserved: [u32; 3],
+    pub flags: u16,
+    pub exclude_bitmap: u32,
+    pub block_bitmap_csum: u16,
+    pub inode_bitmap_csum: u16,
+    pub itable_unused: u16,
+    pub checksum: u16,
+    //
+    // These fields are valid if the 64-bit feature is enabled.
+    //
+    pub block_bitmap_hi: u32,
+    pub inode_bitmap_hi: u32,
+    pub inode_table_hi: u32,
+    pub free_blocks_count_hi: u16,
+    pub free_inodes_count_hi: u16,
+    pub dirs_count_hi: u16,
+    pub itable_unused_hi: u16,
+    pub exclude_bitmap_hi: u32,
+    pub block_bitmap_csum_hi: u16,
+    pub inode_bitmap_csum_hi: u16,
+    reserved: u32,
+}
+
+impl RawGroupDescriptor {
+    /// Computes the checksum of the descriptor of the `idx`-th block group.
+    ///
+    /// Returns `None` if the group descriptors have no checksums.
+    pub(super) fn compute_checksum(
+        &self,
+        idx: usize,
+        desc_size: usize,
+        metadata_checksum: MetadataChecksum,
+    ) -> Option<u16> {
+        let bytes = &self.as_bytes()[..desc_size];
+        let idx_bytes = (idx as u32).to_le_bytes();
+        match metadata_checksum {
+            MetadataChecksum::None => None,
+            MetadataChecksum::Crc16 { uuid } => {
+                let mut crc = crc16(!0, &uuid);
+                crc = crc16(crc, &idx_bytes);
+                crc = crc16(crc, &bytes[..CHECKSUM_OFFSET]);
+                crc = crc16(crc, &bytes[CHECKSUM_OFFSET + 2..]);
+                Some(crc)
+            }
+            MetadataChecksum::Crc32c { seed } => {
+                let mut crc = crc32c(seed, &idx_bytes);
+                crc = crc32c(crc, &bytes[..CHECKSUM_OFFSET]);
+                crc = crc32c(crc, &[0u8; 2]);
+                crc = crc32c(crc, &bytes[CHECKSUM_OFFSET + 2..]);
+                Some((crc & 0xffff) as u16)
+            }
+        }
+    }
 }
 
 impl From<&GroupDescriptor> for RawGroupDescriptor {
@@ -519,8 +847,14 @@ impl From<&GroupDescriptor> for RawGroupDescriptor {
             free_blocks_count: desc.free_blocks_count,
             free_inodes_count: desc.free_inodes_count,
             dirs_count: desc.dirs_count,
-            pad: 0u16,
-            reserved: [0u32; 3],
+            flags: desc.flags.bits(),
+            exclude_bitmap: desc.exclude_bitmap_bid,
+            block_bitmap_csum: desc.block_bitmap_csum as u16,
+            inode_bitmap_csum: desc.inode_bitmap_csum as u16,
+            itable_unused: desc.itable_unused,
+            block_bitmap_csum_hi: (desc.block_bitmap_csum >> 16) as u16,
+            inode_bitmap_csum_hi: (desc.inode_bitmap_csum >> 16) as u16,
+            ..Self::new_zeroed()
         }
     }
 }
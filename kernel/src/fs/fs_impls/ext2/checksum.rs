@@ -0,0 +1,91 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The checksum algorithms used by the metadata of Ext4.
+//!
+//! Like Linux, the functions here compute the raw CRC values, i.e., neither the
+//! initial value nor the result is inverted.
+
+/// Computes the CRC32C (Castagnoli) checksum of `data`, continuing from `crc`.
+pub(super) fn crc32c(crc: u32, data: &[u8]) -> u32 {
+    data.iter().fold(crc, |crc, byte| {
+        CRC32C_TABLE[((crc ^ *byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Computes the CRC16 (ANSI) checksum of `data`, continuing from `crc`.
+pub(super) fn crc16(crc: u16, data: &[u8]) -> u16 {
+    data.iter().fold(crc, |crc, byte| {
+        CRC16_TABLE[((crc ^ *byte as u16) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// The reversed polynomial of CRC32C.
+const CRC32C_POLY: u32 = 0x82f6_3b78;
+
+/// The reversed polynomial of CRC16.
+const CRC16_POLY: u16 = 0xa001;
+
+static CRC32C_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ CRC32C_POLY
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+static CRC16_TABLE: [u16; 256] = {
+    let mut table = [0u16; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u16;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ CRC16_POLY
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    #[ktest]
+    fn crc32c_check_value() {
+        // The standard check value of CRC32C is computed with the inverted initial value
+        // and the inverted result.
+        assert_eq!(!crc32c(!0, b"123456789"), 0xe306_9283);
+        // Checksums can be computed incrementally.
+        assert_eq!(
+            crc32c(crc32c(!0, b"1234"), b"56789"),
+            crc32c(!0, b"123456789")
+        );
+    }
+
+    #[ktest]
+    fn crc16_check_value() {
+        assert_eq!(crc16(0, b"123456789"), 0xbb3d);
+        assert_eq!(crc16(!0, b"123456789"), 0x4b37);
+    }
+}
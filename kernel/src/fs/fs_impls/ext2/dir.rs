@@ -2,7 +2,7 @@
 
 #![expect(dead_code)]
 
-use super::{inode::MAX_FNAME_LEN, prelude::*};
+use super::{checksum::crc32c, inode::MAX_FNAME_LEN, prelude::*};
 
 /// The data structure in a directory's data block. It is stored in a linked list.
 ///
@@ -12,8 +12,10 @@ use super::{inode::MAX_FNAME_LEN, prelude::*};
 pub struct DirEntry {
     /// The header part.
     header: DirEntryHeader,
-    /// Name of the entry, up to 255 bytes (excluding the null terminator).
-    name: CStr256,
+    /// Name of the entry, up to 255 bytes.
+    ///
+    /// The name may contain arbitrary bytes if it is encrypted.
+    name: [u8; MAX_FNAME_LEN],
 }
 
 impl DirEntry {
@@ -23,22 +25,24 @@ impl DirEntry {
 
     /// Constructs a new `DirEntry` object with the specified inode (`ino`),
     /// name (`name`), and file type (`inode_type`).
-    pub(super) fn new(ino: u32, name: &str, inode_type: InodeType) -> Self {
+    pub(super) fn new(ino: u32, name: &[u8], inode_type: InodeType) -> Self {
         let header = DirEntryHeader::new(ino, inode_type, name.len());
+        let mut name_buf = [0; MAX_FNAME_LEN];
+        name_buf[..name.len()].copy_from_slice(name);
         Self {
             header,
-            name: CStr256::from(name),
+            name: name_buf,
         }
     }
 
     /// Constructs a `DirEntry` with the name "." and `self_ino` as its inode.
     pub(super) fn self_entry(self_ino: u32) -> Self {
-        Self::new(self_ino, ".", InodeType::Dir)
+        Self::new(self_ino, b".", InodeType::Dir)
     }
 
     /// Constructs a `DirEntry` with the name ".." and `parent_ino` as its inode.
     pub(super) fn parent_entry(parent_ino: u32) -> Self {
-        Self::new(parent_ino, "..", InodeType::Dir)
+        Self::new(parent_ino, b"..", InodeType::Dir)
     }
 
     /// Returns a reference to the header.
@@ -52,8 +56,8 @@ impl DirEntry {
     }
 
     /// Returns the name.
-    pub fn name(&self) -> &str {
-        self.name.as_str().unwrap()
+    pub fn name(&self) -> &[u8] {
+        &self.name[..self.header.name_len as usize]
     }
 
     /// Returns the inode type of the entry.
@@ -106,6 +110,62 @@ impl DirEntryHeader {
     }
 }
 
+/// The entry at the end of each directory block that holds the checksum of the block.
+///
+/// It looks like an unused entry to the readers that are unaware of checksums.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+struct DirEntryTail {
+    /// Always zero.
+    reserved_zero1: u32,
+    /// Always 12.
+    record_len: u16,
+    /// Always zero.
+    reserved_zero2: u8,
+    /// Always 0xDE.
+    reserved_ft: u8,
+    /// The checksum of the directory block.
+    checksum: u32,
+}
+
+impl DirEntryTail {
+    const LEN: usize = size_of::<Self>();
+    const FILE_TYPE: u8 = 0xde;
+
+    fn new() -> Self {
+        Self {
+            reserved_zero1: 0,
+            record_len: Self::LEN as _,
+            reserved_zero2: 0,
+            reserved_ft: Self::FILE_TYPE,
+            checksum: 0,
+        }
+    }
+
+    /// Reads the tail at the end of the directory block, if any.
+    fn read(block: &[u8]) -> Option<Self> {
+        let tail = Self::from_bytes(&block[block.len() - Self::LEN..]);
+        let is_valid = tail.reserved_zero1 == 0
+            && tail.record_len as usize == Self::LEN
+            && tail.reserved_zero2 == 0
+            && tail.reserved_ft == Self::FILE_TYPE;
+        is_valid.then_some(tail)
+    }
+}
+
+/// Updates the checksum in the tail of the directory block.
+///
+/// Nothing is done if the block does not end with a tail.
+pub(super) fn set_tail_checksum(block: &mut [u8], seed: u32) {
+    let Some(mut tail) = DirEntryTail::read(block) else {
+        return;
+    };
+
+    let tail_offset = block.len() - DirEntryTail::LEN;
+    tail.checksum = crc32c(seed, &block[..tail_offset]);
+    block[tail_offset..].copy_from_slice(tail.as_bytes());
+}
+
 /// The type indicator in the `DirEntry`.
 #[repr(u8)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -199,44 +259,49 @@ impl<'a> DirEntryReader<'a> {
         }
     }
 
-    /// Returns an iterator for iterating `DirEntry`s.
-    pub fn iter_entries(&'a mut self) -> impl Iterator<Item = DirEntry> + 'a {
+    /// Returns an iterator for iterating `DirEntry`s along with their offsets.
+    pub fn iter_entries(&'a mut self) -> impl Iterator<Item = (usize, DirEntry)> + 'a {
         let iter = self.iter();
         iter.filter_map(|entry_item| match self.read_name(&entry_item) {
-            Ok(name_buf) => Some(DirEntry {
-                header: entry_item.header,
-                name: CStr256::from(name_buf),
-            }),
+            Ok(name_buf) => {
+                let mut name = [0; MAX_FNAME_LEN];
+                name[..name_buf.len()].copy_from_slice(name_buf);
+                Some((
+                    entry_item.offset,
+                    DirEntry {
+                        header: entry_item.header,
+                        name,
+                    },
+                ))
+            }
             Err(_) => None,
         })
     }
 
     /// Whether the directory contains an entry with the given name.
-    pub fn contains_entry(&mut self, name: &str) -> bool {
+    pub fn contains_entry(&mut self, name: &[u8]) -> bool {
         let mut iter = self.iter();
         iter.any(|entry_item| {
             if entry_item.name_len() != name.len() {
                 return false;
             }
             match self.read_name(&entry_item) {
-                Ok(name_buf) => name_buf == name.as_bytes(),
+                Ok(name_buf) => name_buf == name,
                 Err(_) => false,
             }
         })
     }
 
     /// Returns the target entry with the given name.
-    pub fn find_entry_item(&mut self, name: &str) -> Option<DirEntryItem> {
+    pub fn find_entry_item(&mut self, name: &[u8]) -> Option<DirEntryItem> {
         let mut iter = self.iter();
-        let name_len = name.len();
-        let name_bytes = name.as_bytes();
         iter.find(|entry_item| {
-            if entry_item.name_len() != name_len {
+            if entry_item.name_len() != name.len() {
                 return false;
             }
 
             match self.read_name(entry_item) {
-                Ok(name_buf) => name_buf == name_bytes,
+                Ok(name_buf) => name_buf == name,
                 Err(_) => false,
             }
         })
@@ -377,24 +442,42 @@ pub struct DirEntryWriter<'a> {
     page_cache: &'a PageCache,
     offset: usize,
     name_buf: Option<[u8; MAX_FNAME_LEN]>,
+    has_tail: bool,
 }
 
 impl<'a> DirEntryWriter<'a> {
     /// Constructs a writer with the given page cache and offset.
-    pub(super) fn new(page_cache: &'a PageCache, from_offset: usize) -> Self {
+    ///
+    /// If `has_tail` is true, each directory block ends with a `DirEntryTail`.
+    pub(super) fn new(page_cache: &'a PageCache, from_offset: usize, has_tail: bool) -> Self {
         Self {
             page_cache,
             offset: from_offset,
             name_buf: None,
+            has_tail,
         }
     }
 
+    /// Returns the length of the tail in each directory block.
+    fn tail_len(&self) -> usize {
+        if self.has_tail { DirEntryTail::LEN } else { 0 }
+    }
+
+    /// Writes a `DirEntryTail` at the end of the block starting from `block_offset`.
+    fn write_tail(&self, block_offset: usize) -> Result<()> {
+        let tail_offset = block_offset + BLOCK_SIZE - DirEntryTail::LEN;
+        self.page_cache
+            .pages()
+            .write_val(tail_offset, &DirEntryTail::new())?;
+        Ok(())
+    }
+
     /// Writes a `DirEntry` at the current offset. The name is written after the header.
-    pub fn write_entry(&mut self, header: &DirEntryHeader, name: &str) -> Result<()> {
+    pub fn write_entry(&mut self, header: &DirEntryHeader, name: &[u8]) -> Result<()> {
         self.page_cache.pages().write_val(self.offset, header)?;
         self.page_cache
             .pages()
-            .write_bytes(self.offset + DirEntry::HEADER_LEN, name.as_bytes())?;
+            .write_bytes(self.offset + DirEntry::HEADER_LEN, name)?;
 
         self.offset += header.record_len as usize;
         Ok(())
@@ -414,12 +497,64 @@ impl<'a> DirEntryWriter<'a> {
         self.page_cache.pages().resize(BLOCK_SIZE)?;
 
         let self_header = DirEntryHeader::new(self_ino, InodeType::Dir, 1);
-        self.write_entry(&self_header, ".")?;
+        self.write_entry(&self_header, b".")?;
         debug_assert_eq!(self.offset, DirEntry::PARENT_OFFSET);
 
         let mut parent_header = DirEntryHeader::new(parent_ino, InodeType::Dir, 2);
-        parent_header.record_len = (BLOCK_SIZE - self.offset) as _;
-        self.write_entry(&parent_header, "..")
+        parent_header.record_len = (BLOCK_SIZE - self.tail_len() - self.offset) as _;
+        self.write_entry(&parent_header, b"..")?;
+
+        if self.has_tail {
+            self.write_tail(0)?;
+        }
+        Ok(())
+    }
+
+    /// Adds a `DirEntryTail` to each directory block that does not end with one.
+    ///
+    /// The space of the tail is taken from the last entry in the block.
+    pub fn add_tails(&mut self) -> Result<()> {
+        let mut block = vec![0u8; BLOCK_SIZE];
+        let size = self.page_cache.pages().size();
+        for block_offset in (0..size).step_by(BLOCK_SIZE) {
+            self.page_cache
+                .pages()
+                .read_bytes(block_offset, &mut block)?;
+            if DirEntryTail::read(&block).is_some() {
+                continue;
+            }
+
+            // Finds the last entry in the block.
+            let mut offset = 0;
+            let last_header = loop {
+                if offset + DirEntry::HEADER_LEN > BLOCK_SIZE {
+                    return_errno_with_message!(Errno::EUCLEAN, "corrupted directory block");
+                }
+                let header = DirEntryHeader::from_first_bytes(&block[offset..]);
+                let record_len = header.record_len as usize;
+                if record_len == 0 || offset + record_len > BLOCK_SIZE {
+                    return_errno_with_message!(Errno::EUCLEAN, "corrupted directory block");
+                }
+                if offset + record_len == BLOCK_SIZE {
+                    break header;
+                }
+                offset += record_len;
+            };
+
+            let mut last_entry = DirEntryItem {
+                header: last_header,
+                offset: block_offset + offset,
+            };
+            if last_entry.gap_len() < DirEntryTail::LEN {
+                return_errno_with_message!(Errno::ENOSPC, "no space for the directory checksum");
+            }
+            last_entry.set_record_len(last_entry.record_len() - DirEntryTail::LEN);
+            self.offset = last_entry.offset;
+            self.write_header_only(&last_entry.header)?;
+            self.write_tail(block_offset)?;
+        }
+
+        Ok(())
     }
 
     /// Appends a new `DirEntry` starting from the current offset.
@@ -430,12 +565,11 @@ impl<'a> DirEntryWriter<'a> {
     pub fn append_new_entry(
         &mut self,
         header: DirEntryHeader,
-        name: &str,
+        name: &[u8],
         check_existence: bool,
     ) -> Result<()> {
         let name_len = name.len();
         debug_assert_eq!(header.name_len as usize, name_len);
-        let name_bytes = name.as_bytes();
         let mut entry_item_with_enough_gap = None;
         for entry_item in DirEntryReader::new(self.page_cache, self.offset).iter() {
             if entry_item_with_enough_gap.is_none()
@@ -449,7 +583,7 @@ impl<'a> DirEntryWriter<'a> {
 
             if check_existence
                 && entry_item.name_len() == name_len
-                && self.read_name(&entry_item)? == name_bytes
+                && self.read_name(&entry_item)? == name
             {
                 return_errno!(Errno::EEXIST);
             }
@@ -467,7 +601,7 @@ impl<'a> DirEntryWriter<'a> {
         &mut self,
         mut entry_with_enough_gap: DirEntryItem,
         mut header: DirEntryHeader,
-        name: &str,
+        name: &[u8],
     ) -> Result<()> {
         // Write in the gap between existing entries.
         header.record_len = entry_with_enough_gap.gap_len() as u16;
@@ -478,22 +612,26 @@ impl<'a> DirEntryWriter<'a> {
         self.write_entry(&header, name)
     }
 
-    fn append_entry_in_the_end(&mut self, mut header: DirEntryHeader, name: &str) -> Result<()> {
+    fn append_entry_in_the_end(&mut self, mut header: DirEntryHeader, name: &[u8]) -> Result<()> {
         // Resize and append it at the new block.
         let old_size = self.page_cache.pages().size();
         let new_size = old_size + BLOCK_SIZE;
         self.page_cache.resize(new_size)?;
-        header.record_len = BLOCK_SIZE as _;
+        header.record_len = (BLOCK_SIZE - self.tail_len()) as _;
 
         self.offset = old_size;
-        self.write_entry(&header, name)
+        self.write_entry(&header, name)?;
+
+        if self.has_tail {
+            self.write_tail(old_size)?;
+        }
+        Ok(())
     }
 
     /// Removes and returns an existing `DirEntry` indicated by `name`.
-    pub fn remove_entry(&mut self, name: &str) -> Result<DirEntryItem> {
+    pub fn remove_entry(&mut self, name: &[u8]) -> Result<DirEntryItem> {
         let mut pre_entry_item = None;
         let name_len = name.len();
-        let name_bytes = name.as_bytes();
         let mut iter = DirEntryReader::new(self.page_cache, DirEntry::PARENT_OFFSET).iter();
         let Some(target_entry_item) = iter.find(|entry| {
             if entry.offset < self.offset {
@@ -502,7 +640,7 @@ impl<'a> DirEntryWriter<'a> {
 
             entry.offset == self.offset
                 && entry.name_len() == name_len
-                && self.read_name(entry).unwrap() == name_bytes
+                && self.read_name(entry).unwrap() == name
         }) else {
             return_errno!(Errno::ENOENT);
         };
@@ -512,17 +650,24 @@ impl<'a> DirEntryWriter<'a> {
         let pre_offset = pre_entry_item.offset;
         if is_last_entry {
             // Shrink the size.
-            let new_size = pre_offset.align_up(BLOCK_SIZE);
+            let new_size = pre_offset.align_down(BLOCK_SIZE) + BLOCK_SIZE;
             self.page_cache.resize(new_size)?;
-            pre_entry_item.set_record_len(new_size - pre_offset);
+            pre_entry_item.set_record_len(new_size - self.tail_len() - pre_offset);
             self.offset = pre_offset;
             self.write_header_only(&pre_entry_item.header)?;
-        } else {
+        } else if pre_offset + pre_entry_item.record_len() == target_entry_item.offset {
             // Update the previous entry.
             pre_entry_item
                 .set_record_len(pre_entry_item.record_len() + target_entry_item.record_len());
             self.offset = pre_offset;
             self.write_header_only(&pre_entry_item.header)?;
+        } else {
+            // The previous entry is in another block or separated by unused entries,
+            // so the target entry is marked as unused instead.
+            let mut target_header = target_entry_item.header;
+            target_header.ino = 0;
+            self.offset = target_entry_item.offset;
+            self.write_header_only(&target_header)?;
         }
 
         Ok(target_entry_item)
@@ -532,7 +677,7 @@ impl<'a> DirEntryWriter<'a> {
     ///
     /// It will moves the `DirEntry` to another position,
     /// if the record length is not big enough.
-    pub fn rename_entry(&mut self, old_name: &str, new_name: &str) -> Result<()> {
+    pub fn rename_entry(&mut self, old_name: &[u8], new_name: &[u8]) -> Result<()> {
         let entry_item = DirEntryReader::new(self.page_cache, self.offset)
             .find_entry_item(old_name)
             .ok_or(Error::new(Errno::ENOENT))?;
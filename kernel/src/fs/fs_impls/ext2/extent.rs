@@ -0,0 +1,645 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The extent tree of Ext4.
+//!
+//! An extent maps a range of consecutive blocks in a file to a range of consecutive blocks
+//! on the device. The extents of a file are organized as a B+ tree, whose root is stored
+//! in the block pointers of the inode, and whose other nodes occupy whole blocks.
+//!
+//! The whole tree is loaded into memory when the inode is loaded. When the extents are
+//! modified, the tree is rebuilt and written back as the inode metadata is synced.
+//!
+//! Reference: <https://docs.kernel.org/filesystems/ext4/dynamic.html#extent-tree>
+
+use ostd::const_assert;
+
+use super::{
+    block_ptr::{BlockPtrs, Ext2Bid},
+    checksum::crc32c,
+    fs::Ext2,
+    prelude::*,
+};
+
+/// The magic number of the extent header.
+const EXTENT_MAGIC: u16 = 0xf30a;
+
+/// The maximum depth of the extent tree.
+const MAX_DEPTH: u16 = 5;
+
+/// The maximum number of entries in the root node, which is stored in the inode.
+const ROOT_MAX_ENTRIES: usize = (size_of::<BlockPtrs>() - HEADER_SIZE) / ENTRY_SIZE;
+
+/// The maximum number of entries in a node that occupies a whole block.
+const NODE_MAX_ENTRIES: usize = (BLOCK_SIZE - HEADER_SIZE) / ENTRY_SIZE;
+
+/// The maximum length of an initialized extent.
+const MAX_INIT_LEN: u32 = 1 << 15;
+
+/// The maximum length of an uninitialized (i.e., unwritten) extent.
+const MAX_UNINIT_LEN: u32 = (1 << 15) - 1;
+
+const HEADER_SIZE: usize = size_of::<ExtentHeader>();
+const ENTRY_SIZE: usize = size_of::<RawExtent>();
+
+const_assert!(size_of::<ExtentIndex>() == ENTRY_SIZE);
+
+/// The extent tree of an inode.
+#[derive(Debug)]
+pub(super) struct ExtentTree {
+    /// The extents sorted by their starting block IDs in the file.
+    extents: Vec<Extent>,
+    /// The blocks occupied by the nodes other than the root.
+    node_bids: Vec<Ext2Bid>,
+    is_dirty: bool,
+}
+
+/// An extent that maps `block..block + len` in the file to `start..start + len` on the device.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Extent {
+    block: Ext2Bid,
+    len: u32,
+    start: Ext2Bid,
+    is_unwritten: bool,
+}
+
+impl Extent {
+    fn end(&self) -> Ext2Bid {
+        self.block + self.len
+    }
+
+    fn max_len(&self) -> u32 {
+        if self.is_unwritten {
+            MAX_UNINIT_LEN
+        } else {
+            MAX_INIT_LEN
+        }
+    }
+
+    /// Tries to merge the `next` extent into this extent.
+    fn try_merge(&mut self, next: &Extent) -> bool {
+        if self.end() != next.block
+            || self.start + self.len != next.start
+            || self.is_unwritten != next.is_unwritten
+            || self.len + next.len > self.max_len()
+        {
+            return false;
+        }
+
+        self.len += next.len;
+        true
+    }
+}
+
+/// The mapping of a range of blocks in the file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) enum ExtentMapping {
+    /// The blocks are mapped to the device range.
+    Mapped(Range<Ext2Bid>),
+    /// The blocks are mapped to the device range, but have not been written yet.
+    /// Reading them should return zeros.
+    Unwritten(Range<Ext2Bid>),
+    /// The specified number of blocks are not mapped.
+    Hole(u32),
+}
+
+impl ExtentTree {
+    /// Creates an empty extent tree.
+    pub fn new() -> Self {
+        Self {
+            extents: Vec::new(),
+            node_bids: Vec::new(),
+            is_dirty: true,
+        }
+    }
+
+    /// Initializes the root node of an empty extent tree in the block pointers.
+    pub fn init_root(root: &mut BlockPtrs) {
+        root.as_bytes_mut().fill(0);
+        write_node(root.as_bytes_mut(), 0, ROOT_MAX_ENTRIES, &[]);
+    }
+
+    /// Loads the extent tree whose root is stored in the block pointers.
+    ///
+    /// The other nodes are read with `read_block`. If `csum_seed` is specified,
+    /// the checksums of the nodes are verified.
+    pub fn load(
+        root: &BlockPtrs,
+        read_block: &dyn Fn(Ext2Bid, &mut [u8]) -> Result<()>,
+        csum_seed: Option<u32>,
+    ) -> Result<Self> {
+        let mut tree = Self {
+            extents: Vec::new(),
+            node_bids: Vec::new(),
+            is_dirty: false,
+        };
+
+        let header = ExtentHeader::parse(root.as_bytes(), ROOT_MAX_ENTRIES)?;
+        if header.depth > MAX_DEPTH {
+            return_errno_with_message!(Errno::EUCLEAN, "the extent tree is too deep");
+        }
+        tree.load_node(root.as_bytes(), header.depth, read_block, csum_seed)?;
+
+        let is_sorted = tree
+            .extents
+            .windows(2)
+            .all(|pair| pair[0].end() <= pair[1].block);
+        if !is_sorted {
+            return_errno_with_message!(Errno::EUCLEAN, "the extents overlap");
+        }
+
+        Ok(tree)
+    }
+
+    fn load_node(
+        &mut self,
+        node: &[u8],
+        depth: u16,
+        read_block: &dyn Fn(Ext2Bid, &mut [u8]) -> Result<()>,
+        csum_seed: Option<u32>,
+    ) -> Result<()> {
+        let header = ExtentHeader::read(node);
+        if header.depth != depth {
+            return_errno_with_message!(Errno::EUCLEAN, "bad extent tree depth");
+        }
+        let entries = (0..header.entries as usize)
+            .map(|i| &node[HEADER_SIZE + i * ENTRY_SIZE..HEADER_SIZE + (i + 1) * ENTRY_SIZE]);
+
+        if depth == 0 {
+            for entry in entries {
+                let raw_extent = RawExtent::from_bytes(entry);
+                self.extents.push(raw_extent.to_extent()?);
+            }
+            return Ok(());
+        }
+
+        let mut buf = vec![0u8; BLOCK_SIZE];
+        for entry in entries {
+            let index = ExtentIndex::from_bytes(entry);
+            if index.leaf_hi != 0 || index.leaf_lo == 0 {
+                return_errno_with_message!(Errno::EUCLEAN, "bad extent index");
+            }
+            let bid = index.leaf_lo;
+            read_block(bid, &mut buf)?;
+            ExtentHeader::parse(&buf, NODE_MAX_ENTRIES)?;
+            if let Some(seed) = csum_seed {
+                let (data, tail) = buf.split_at(node_tail_offset(&buf));
+                if crc32c(seed, data).to_le_bytes() != tail[..4] {
+                    return_errno_with_message!(Errno::EBADMSG, "bad extent block checksum");
+                }
+            }
+
+            self.node_bids.push(bid);
+            self.load_node(&buf, depth - 1, read_block, csum_seed)?;
+        }
+
+        Ok(())
+    }
+
+    /// Looks up the mapping of the blocks starting from `bid`.
+    ///
+    /// The returned mapping covers at least one block and at most `max_len` blocks.
+    pub fn lookup(&self, bid: Ext2Bid, max_len: u32) -> ExtentMapping {
+        debug_assert!(max_len > 0);
+
+        let idx = self.extents.partition_point(|extent| extent.end() <= bid);
+        let Some(extent) = self.extents.get(idx) else {
+            return ExtentMapping::Hole(max_len);
+        };
+        if extent.block > bid {
+            return ExtentMapping::Hole((extent.block - bid).min(max_len));
+        }
+
+        let offset = bid - extent.block;
+        let len = (extent.len - offset).min(max_len);
+        let start = extent.start + offset;
+        if extent.is_unwritten {
+            ExtentMapping::Unwritten(start..start + len)
+        } else {
+            ExtentMapping::Mapped(start..start + len)
+        }
+    }
+
+    /// Returns a device block ID that is preferable to be allocated for `bid`.
+    pub fn goal(&self, bid: Ext2Bid) -> Option<Ext2Bid> {
+        let idx = self.extents.partition_point(|extent| extent.block < bid);
+        let extent = self.extents.get(idx.checked_sub(1)?)?;
+        Some(extent.start + (bid - extent.block).min(extent.len))
+    }
+
+    /// Maps the blocks starting from `bid`, which must be in a hole, to the device range.
+    pub fn insert(&mut self, bid: Ext2Bid, device_range: Range<Ext2Bid>) {
+        debug_assert!(matches!(self.lookup(bid, 1), ExtentMapping::Hole(_)));
+
+        let mut extents = Vec::new();
+        let mut current = bid;
+        let mut device_range = device_range;
+        while !device_range.is_empty() {
+            let len = (device_range.len() as u32).min(MAX_INIT_LEN);
+            extents.push(Extent {
+                block: current,
+                len,
+                start: device_range.start,
+                is_unwritten: false,
+            });
+            current += len;
+            device_range.start += len;
+        }
+
+        let idx = self.extents.partition_point(|extent| extent.block < bid);
+        self.extents.splice(idx..idx, extents);
+        self.merge_around(idx.saturating_sub(1)..idx + 2);
+        self.is_dirty = true;
+    }
+
+    /// Marks the unwritten blocks in `range` as written.
+    pub fn mark_written(&mut self, range: Range<Ext2Bid>) {
+        let start_idx = self
+            .extents
+            .partition_point(|extent| extent.end() <= range.start);
+        let mut idx = start_idx;
+        while let Some(extent) = self.extents.get(idx).copied() {
+            if extent.block >= range.end {
+                break;
+            }
+            if !extent.is_unwritten {
+                idx += 1;
+                continue;
+            }
+
+            // Splits the extent into up to three parts, where the middle part is written.
+            let written_start = extent.block.max(range.start);
+            let written_end = extent.end().min(range.end);
+            let mut parts = Vec::with_capacity(3);
+            if extent.block < written_start {
+                parts.push(Extent {
+                    len: written_start - extent.block,
+                    ..extent
+                });
+            }
+            parts.push(Extent {
+                block: written_start,
+                len: written_end - written_start,
+                start: extent.start + (written_start - extent.block),
+                is_unwritten: false,
+            });
+            if written_end < extent.end() {
+                parts.push(Extent {
+                    block: written_end,
+                    len: extent.end() - written_end,
+                    start: extent.start + (written_end - extent.block),
+                    is_unwritten: true,
+                });
+            }
+
+            let nparts = parts.len();
+            self.extents.splice(idx..idx + 1, parts);
+            idx += nparts;
+        }
+
+        self.merge_around(start_idx.saturating_sub(1)..idx + 1);
+        self.is_dirty = true;
+    }
+
+    /// Merges the adjacent extents in `range` if possible.
+    fn merge_around(&mut self, range: Range<usize>) {
+        let mut idx = range.start;
+        let mut end = range.end.min(self.extents.len());
+        while idx + 1 < end {
+            let next = self.extents[idx + 1];
+            if self.extents[idx].try_merge(&next) {
+                self.extents.remove(idx + 1);
+                end -= 1;
+            } else {
+                idx += 1;
+            }
+        }
+    }
+
+    /// Removes the mappings of the blocks starting from `nblocks`,
+    /// returning the device ranges that are no longer used.
+    pub fn truncate(&mut self, nblocks: Ext2Bid) -> Vec<Range<Ext2Bid>> {
+        let idx = self
+            .extents
+            .partition_point(|extent| extent.end() <= nblocks);
+        if idx == self.extents.len() {
+            return Vec::new();
+        }
+
+        let mut freed_ranges = Vec::new();
+        for extent in self.extents.split_off(idx) {
+            if extent.block >= nblocks {
+                freed_ranges.push(extent.start..extent.start + extent.len);
+                continue;
+            }
+
+            // Keeps the part before `nblocks`.
+            let kept_len = nblocks - extent.block;
+            freed_ranges.push(extent.start + kept_len..extent.start + extent.len);
+            self.extents.push(Extent {
+                len: kept_len,
+                ..extent
+            });
+        }
+
+        self.is_dirty = true;
+        freed_ranges
+    }
+
+    /// Returns the number of blocks occupied by the file, including the tree nodes.
+    pub fn nr_blocks(&self) -> u32 {
+        let data_blocks: u32 = self.extents.iter().map(|extent| extent.len).sum();
+        data_blocks + self.node_bids.len() as u32
+    }
+
+    /// Returns whether the tree has been modified since it was loaded or stored.
+    pub fn is_dirty(&self) -> bool {
+        self.is_dirty
+    }
+
+    /// Stores the extent tree, whose root is stored in the block pointers.
+    ///
+    /// The nodes other than the root are allocated or freed as needed. If `csum_seed` is
+    /// specified, the checksums of the nodes are updated.
+    pub fn store(
+        &mut self,
+        root: &mut BlockPtrs,
+        fs: &Ext2,
+        block_group_idx: usize,
+        csum_seed: Option<u32>,
+    ) -> Result<()> {
+        if !self.is_dirty {
+            return Ok(());
+        }
+
+        // Calculates the number of nodes other than the root.
+        let mut nr_nodes = 0;
+        let mut nr_entries = self.extents.len();
+        while nr_entries > ROOT_MAX_ENTRIES {
+            nr_entries = nr_entries.div_ceil(NODE_MAX_ENTRIES);
+            nr_nodes += nr_entries;
+        }
+
+        // Allocates or frees the blocks of the nodes.
+        while self.node_bids.len() > nr_nodes {
+            let bid = self.node_bids.pop().unwrap();
+            fs.free_blocks(bid..bid + 1)?;
+        }
+        while self.node_bids.len() < nr_nodes {
+            let range = fs
+                .alloc_blocks(
+                    block_group_idx,
+                    (nr_nodes - self.node_bids.len()) as Ext2Bid,
+                )
+                .ok_or_else(|| Error::with_message(Errno::ENOSPC, "no space for extent blocks"))?;
+            self.node_bids.extend(range);
+        }
+
+        // Writes the nodes from the leaves to the root. Each entry of the upper level
+        // points to a node of the current level.
+        let mut node_bids = self.node_bids.iter();
+        let mut entries: Vec<[u8; ENTRY_SIZE]> = self
+            .extents
+            .iter()
+            .map(|extent| RawExtent::from_extent(extent).to_bytes())
+            .collect();
+        let mut depth = 0;
+        let mut buf = vec![0u8; BLOCK_SIZE];
+        while entries.len() > ROOT_MAX_ENTRIES {
+            let mut upper_entries = Vec::with_capacity(entries.len().div_ceil(NODE_MAX_ENTRIES));
+            for node_entries in entries.chunks(NODE_MAX_ENTRIES) {
+                let bid = *node_bids.next().unwrap();
+                buf.fill(0);
+                write_node(&mut buf, depth, NODE_MAX_ENTRIES, node_entries);
+                if let Some(seed) = csum_seed {
+                    let tail_offset = node_tail_offset(&buf);
+                    let checksum = crc32c(seed, &buf[..tail_offset]);
+                    buf[tail_offset..tail_offset + 4].copy_from_slice(&checksum.to_le_bytes());
+                }
+                fs.write_metadata_bytes_async(bid as usize * BLOCK_SIZE, &buf)?
+                    .wait()
+                    .ok_or_else(|| {
+                        Error::with_message(Errno::EIO, "failed to write extent block")
+                    })?;
+
+                let index = ExtentIndex {
+                    // Both leaf entries and index entries start with the first block ID
+                    // they cover.
+                    block: u32::from_first_bytes(&node_entries[0]),
+                    leaf_lo: bid,
+                    leaf_hi: 0,
+                    unused: 0,
+                };
+                let mut index_bytes = [0u8; ENTRY_SIZE];
+                index_bytes.copy_from_slice(index.as_bytes());
+                upper_entries.push(index_bytes);
+            }
+            entries = upper_entries;
+            depth += 1;
+        }
+
+        root.as_bytes_mut().fill(0);
+        write_node(root.as_bytes_mut(), depth, ROOT_MAX_ENTRIES, &entries);
+
+        self.is_dirty = false;
+        Ok(())
+    }
+}
+
+/// Writes the header and the raw entries of a node.
+fn write_node(node: &mut [u8], depth: u16, max_entries: usize, entries: &[[u8; ENTRY_SIZE]]) {
+    let header = ExtentHeader::new(entries.len(), max_entries, depth);
+    node[..HEADER_SIZE].copy_from_slice(header.as_bytes());
+    for (i, entry) in entries.iter().enumerate() {
+        let offset = HEADER_SIZE + i * ENTRY_SIZE;
+        node[offset..offset + ENTRY_SIZE].copy_from_slice(entry);
+    }
+}
+
+/// Returns the offset of the checksum in a node that occupies a whole block.
+fn node_tail_offset(node: &[u8]) -> usize {
+    HEADER_SIZE + ExtentHeader::read(node).max as usize * ENTRY_SIZE
+}
+
+/// The header of a node in the extent tree.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+struct ExtentHeader {
+    magic: u16,
+    entries: u16,
+    max: u16,
+    depth: u16,
+    generation: u32,
+}
+
+impl ExtentHeader {
+    fn new(entries: usize, max: usize, depth: u16) -> Self {
+        Self {
+            magic: EXTENT_MAGIC,
+            entries: entries as u16,
+            max: max as u16,
+            depth,
+            generation: 0,
+        }
+    }
+
+    fn read(node: &[u8]) -> Self {
+        Self::from_bytes(&node[..HEADER_SIZE])
+    }
+
+    /// Reads and validates the header of a node that can hold at most `max_entries` entries.
+    fn parse(node: &[u8], max_entries: usize) -> Result<Self> {
+        let header = Self::read(node);
+        if header.magic != EXTENT_MAGIC {
+            return_errno_with_message!(Errno::EUCLEAN, "bad extent header magic");
+        }
+        if header.max as usize > max_entries || header.entries > header.max {
+            return_errno_with_message!(Errno::EUCLEAN, "bad extent header entries");
+        }
+        Ok(header)
+    }
+}
+
+/// The leaf entry of the extent tree.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+struct RawExtent {
+    block: u32,
+    len: u16,
+    start_hi: u16,
+    start_lo: u32,
+}
+
+impl RawExtent {
+    fn to_extent(self) -> Result<Extent> {
+        if self.start_hi != 0 || self.len == 0 {
+            return_errno_with_message!(Errno::EUCLEAN, "bad extent");
+        }
+
+        let (len, is_unwritten) = if self.len as u32 > MAX_INIT_LEN {
+            (self.len as u32 - MAX_INIT_LEN, true)
+        } else {
+            (self.len as u32, false)
+        };
+        if self.block.checked_add(len).is_none() || self.start_lo.checked_add(len).is_none() {
+            return_errno_with_message!(Errno::EUCLEAN, "bad extent");
+        }
+
+        Ok(Extent {
+            block: self.block,
+            len,
+            start: self.start_lo,
+            is_unwritten,
+        })
+    }
+
+    fn from_extent(extent: &Extent) -> Self {
+        Self {
+            block: extent.block,
+            len: if extent.is_unwritten {
+                (extent.len + MAX_INIT_LEN) as u16
+            } else {
+                extent.len as u16
+            },
+            start_hi: 0,
+            start_lo: extent.start,
+        }
+    }
+
+    fn to_bytes(self) -> [u8; ENTRY_SIZE] {
+        let mut bytes = [0u8; ENTRY_SIZE];
+        bytes.copy_from_slice(self.as_bytes());
+        bytes
+    }
+}
+
+/// The index entry of the extent tree.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+struct ExtentIndex {
+    block: u32,
+    leaf_lo: u32,
+    leaf_hi: u16,
+    unused: u16,
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    fn extent(block: Ext2Bid, len: u32, start: Ext2Bid, is_unwritten: bool) -> Extent {
+        Extent {
+            block,
+            len,
+            start,
+            is_unwritten,
+        }
+    }
+
+    #[ktest]
+    fn lookup_and_insert() {
+        let mut tree = ExtentTree::new();
+        assert_eq!(tree.lookup(0, 8), ExtentMapping::Hole(8));
+
+        tree.insert(0, 100..104);
+        tree.insert(8, 200..202);
+        assert_eq!(tree.lookup(0, 8), ExtentMapping::Mapped(100..104));
+        assert_eq!(tree.lookup(2, 1), ExtentMapping::Mapped(102..103));
+        assert_eq!(tree.lookup(4, 8), ExtentMapping::Hole(4));
+        assert_eq!(tree.lookup(9, 8), ExtentMapping::Mapped(201..202));
+        assert_eq!(tree.lookup(10, 8), ExtentMapping::Hole(8));
+        assert_eq!(tree.goal(4), Some(104));
+        assert_eq!(tree.goal(0), None);
+
+        // The adjacent extents are merged.
+        tree.insert(4, 104..108);
+        assert_eq!(tree.extents.len(), 2);
+        assert_eq!(tree.lookup(0, 16), ExtentMapping::Mapped(100..108));
+        assert_eq!(tree.nr_blocks(), 10);
+    }
+
+    #[ktest]
+    fn insert_long_range() {
+        let mut tree = ExtentTree::new();
+        tree.insert(0, 0..MAX_INIT_LEN * 2 + 1);
+        assert_eq!(tree.extents.len(), 3);
+        assert_eq!(tree.nr_blocks(), MAX_INIT_LEN * 2 + 1);
+    }
+
+    #[ktest]
+    fn mark_written() {
+        let mut tree = ExtentTree::new();
+        tree.extents.push(extent(0, 10, 100, true));
+
+        tree.mark_written(4..6);
+        assert_eq!(
+            tree.extents,
+            vec![
+                extent(0, 4, 100, true),
+                extent(4, 2, 104, false),
+                extent(6, 4, 106, true),
+            ]
+        );
+        assert_eq!(tree.lookup(0, 8), ExtentMapping::Unwritten(100..104));
+        assert_eq!(tree.lookup(4, 8), ExtentMapping::Mapped(104..106));
+
+        tree.mark_written(0..4);
+        tree.mark_written(6..10);
+        assert_eq!(tree.extents, vec![extent(0, 10, 100, false)]);
+    }
+
+    #[ktest]
+    fn truncate() {
+        let mut tree = ExtentTree::new();
+        tree.insert(0, 100..110);
+        tree.insert(20, 300..310);
+
+        assert_eq!(tree.truncate(30), vec![]);
+        assert_eq!(tree.truncate(25), vec![305..310]);
+        assert_eq!(tree.truncate(5), vec![105..110, 300..305]);
+        assert_eq!(tree.extents, vec![extent(0, 5, 100, false)]);
+        assert_eq!(tree.truncate(0), vec![100..105]);
+        assert!(tree.extents.is_empty());
+    }
+}
@@ -3,16 +3,24 @@
 #![expect(dead_code)]
 
 use device_id::DeviceId;
+use ostd::mm::{HasSize, io::util::HasVmReaderWriter};
 
 use super::{
     block_group::{BlockGroup, RawGroupDescriptor},
     block_ptr::Ext2Bid,
+    checksum::crc32c,
+    extent::ExtentTree,
     inode::{FilePerm, Inode, InodeDesc, RawInode},
+    journal::Journal,
     prelude::*,
-    super_block::{RawSuperBlock, SUPER_BLOCK_OFFSET, SuperBlock},
+    super_block::{
+        FeatureInCompatSet, MetadataChecksum, RawSuperBlock, SUPER_BLOCK_OFFSET, SuperBlock,
+    },
 };
 use crate::fs::vfs::{
     file_system::{FileSystem, FsEventSubscriberStats, FsFlags},
+    fscrypt::FscryptKeyring,
+    quota::{DiskQuotas, QuotaFormat, QuotaType},
     registry::{FsProperties, FsType},
 };
 
@@ -29,30 +37,64 @@ pub struct Ext2 {
     blocks_per_group: Ext2Bid,
     inode_size: usize,
     block_size: usize,
+    desc_size: usize,
+    metadata_checksum: MetadataChecksum,
     group_descriptors_segment: USegment,
+    journal: Option<Journal>,
+    /// The freed blocks to be discarded after the metadata are synced,
+    /// or `None` if the online discard is disabled.
+    pending_discards: Option<Mutex<Vec<Range<Ext2Bid>>>>,
+    disk_quotas: DiskQuotas,
+    fscrypt_keyring: FscryptKeyring,
     fs_event_subscriber_stats: FsEventSubscriberStats,
+    fs_type_name: &'static str,
     self_ref: Weak<Self>,
 }
 
 impl Ext2 {
     /// Opens and loads an Ext2 from the `block_device`.
     pub fn open(block_device: Arc<dyn BlockDevice>) -> Result<Arc<Self>> {
+        Self::open_as(block_device, "ext2", Ext2MountOptions::default())
+    }
+
+    /// Opens and loads an Ext2 from the `block_device` with the mount options,
+    /// which is reported as a filesystem of type `fs_type_name`.
+    pub(super) fn open_as(
+        block_device: Arc<dyn BlockDevice>,
+        fs_type_name: &'static str,
+        options: Ext2MountOptions,
+    ) -> Result<Arc<Self>> {
         // Load the superblock
         // TODO: if the main superblock is corrupted, should we load the backup?
-        let super_block = {
+        let read_super_block = || -> Result<SuperBlock> {
             let raw_super_block = block_device.read_val::<RawSuperBlock>(SUPER_BLOCK_OFFSET)?;
-            SuperBlock::try_from(raw_super_block)?
+            SuperBlock::try_from(raw_super_block)
         };
+        let mut super_block = read_super_block()?;
         assert_eq!(
             super_block.block_size(),
             BLOCK_SIZE,
             "currently only support 4096-byte block size"
         );
 
+        // Replays the journal before any other metadata are loaded.
+        let journal = if super_block.has_journal() {
+            let journal = Journal::load(block_device.as_ref(), &super_block)?;
+            super_block = read_super_block()?;
+            if !super_block.needs_recovery() {
+                // The journal must be replayed if the filesystem is not unmounted cleanly.
+                super_block.set_needs_recovery();
+                let raw_super_block = RawSuperBlock::from(&super_block);
+                block_device.write_bytes(SUPER_BLOCK_OFFSET, raw_super_block.as_bytes())?;
+            }
+            Some(journal)
+        } else {
+            None
+        };
+
         let group_descriptors_segment: USegment = {
-            let npages = ((super_block.block_groups_count() as usize)
-                * size_of::<RawGroupDescriptor>())
-            .div_ceil(BLOCK_SIZE);
+            let npages = ((super_block.block_groups_count() as usize) * super_block.desc_size())
+                .div_ceil(BLOCK_SIZE);
             let segment = FrameAllocOptions::new()
                 .zeroed(false)
                 .alloc_segment(npages)?;
@@ -87,23 +129,65 @@ impl Ext2 {
             Ok(block_groups)
         };
 
+        let quota_inos = super_block.quota_inos();
+        let disk_quotas = if quota_inos.iter().any(|&ino| ino != 0) {
+            DiskQuotas::new_with_hidden_files()
+        } else {
+            DiskQuotas::new()
+        };
+
+        let pending_discards = if !options.discard {
+            None
+        } else if block_device.metadata().max_discard_sectors == 0 {
+            warn!("the block device cannot discard, so the online discard is disabled");
+            None
+        } else {
+            Some(Mutex::new(Vec::new()))
+        };
+
+        // The metadata of block groups may be corrupted, e.g., with bad checksums.
+        let mut load_result = Ok(());
         let ext2 = Arc::new_cyclic(|weak_ref| Self {
             inodes_per_group: super_block.inodes_per_group(),
             blocks_per_group: super_block.blocks_per_group(),
             inode_size: super_block.inode_size(),
             block_size: super_block.block_size(),
+            desc_size: super_block.desc_size(),
+            metadata_checksum: super_block.metadata_checksum(),
             block_groups: load_block_groups(
                 weak_ref.clone(),
                 block_device.as_ref(),
                 &group_descriptors_segment,
             )
-            .unwrap(),
+            .unwrap_or_else(|err| {
+                load_result = Err(err);
+                Vec::new()
+            }),
             block_device,
             super_block: RwMutex::new(Dirty::new(super_block)),
             group_descriptors_segment,
+            journal,
+            pending_discards,
+            disk_quotas,
+            fscrypt_keyring: FscryptKeyring::new(),
             fs_event_subscriber_stats: FsEventSubscriberStats::new(),
+            fs_type_name,
             self_ref: weak_ref.clone(),
         });
+        load_result?;
+
+        // The hidden quota files are loaded on mounting, so the usage is always tracked.
+        for (type_, ino) in [QuotaType::User, QuotaType::Group]
+            .into_iter()
+            .zip(quota_inos)
+        {
+            if ino != 0 {
+                let file = ext2.lookup_inode(ino)?;
+                ext2.disk_quotas
+                    .load_hidden_file(type_, file, QuotaFormat::VfsV1)?;
+            }
+        }
+
         Ok(ext2)
     }
 
@@ -127,6 +211,35 @@ impl Ext2 {
         self.inode_size
     }
 
+    /// Returns the name of the filesystem type.
+    pub fn fs_type_name(&self) -> &'static str {
+        self.fs_type_name
+    }
+
+    /// Returns the checksum algorithm of the metadata.
+    pub(super) fn metadata_checksum(&self) -> MetadataChecksum {
+        self.metadata_checksum
+    }
+
+    /// Returns the seed of the CRC32C metadata checksums.
+    ///
+    /// Returns `None` if the `metadata_csum` feature is disabled.
+    pub(super) fn csum_seed(&self) -> Option<u32> {
+        match self.metadata_checksum {
+            MetadataChecksum::Crc32c { seed } => Some(seed),
+            _ => None,
+        }
+    }
+
+    /// Returns the seed of the metadata checksums that belong to an inode.
+    ///
+    /// Returns `None` if the `metadata_csum` feature is disabled.
+    pub(super) fn inode_csum_seed(&self, ino: u32, generation: u32) -> Option<u32> {
+        let seed = self.csum_seed()?;
+        let seed = crc32c(seed, &ino.to_le_bytes());
+        Some(crc32c(seed, &generation.to_le_bytes()))
+    }
+
     /// Returns the number of inodes in each block group.
     pub fn inodes_per_group(&self) -> u32 {
         self.inodes_per_group
@@ -163,11 +276,35 @@ impl Ext2 {
     ) -> Result<Arc<Inode>> {
         let (block_group_idx, ino) =
             self.alloc_ino(dir_block_group_idx, inode_type == InodeType::Dir)?;
+        let block_group = &self.block_groups[block_group_idx];
+        // The slot of the inode may contain stale data, including the extra fields.
+        block_group.reset_raw_inode(
+            self.inode_idx(ino),
+            self.super_block.read().want_extra_isize(),
+        );
+
         let inode = {
-            let inode_desc = InodeDesc::new(inode_type, file_perm);
-            Inode::new(ino, block_group_idx, inode_desc, self.self_ref.clone())
+            let mut inode_desc = InodeDesc::new(inode_type, file_perm);
+            let uses_extents = self
+                .super_block
+                .read()
+                .feature_incompat()
+                .contains(FeatureInCompatSet::EXTENTS)
+                && matches!(inode_type, InodeType::File | InodeType::Dir);
+            let extent_tree = if uses_extents {
+                inode_desc.init_extent_root();
+                Some(ExtentTree::new())
+            } else {
+                None
+            };
+            Inode::new(
+                ino,
+                block_group_idx,
+                inode_desc,
+                extent_tree,
+                self.self_ref.clone(),
+            )
         };
-        let block_group = &self.block_groups[block_group_idx];
         block_group.insert_cache(self.inode_idx(ino), inode.clone());
         Ok(inode)
     }
@@ -223,9 +360,16 @@ impl Ext2 {
         block_group_idx: usize,
         raw_descriptor: &RawGroupDescriptor,
     ) -> Result<()> {
-        let offset = block_group_idx * size_of::<RawGroupDescriptor>();
+        let mut raw_descriptor = *raw_descriptor;
+        if let Some(checksum) =
+            raw_descriptor.compute_checksum(block_group_idx, self.desc_size, self.metadata_checksum)
+        {
+            raw_descriptor.checksum = checksum;
+        }
+
+        let offset = block_group_idx * self.desc_size;
         self.group_descriptors_segment
-            .write_val(offset, raw_descriptor)?;
+            .write_bytes(offset, &raw_descriptor.as_bytes()[..self.desc_size])?;
         Ok(())
     }
 
@@ -285,12 +429,20 @@ impl Ext2 {
             self.super_block
                 .write()
                 .dec_free_blocks(range.len() as Ext2Bid);
+            // The reallocated blocks must not be discarded, or their new data will be lost.
+            if let Some(pending_discards) = self.pending_discards.as_ref() {
+                remove_range(&mut pending_discards.lock(), range);
+            }
         }
         allocated_range
     }
 
     /// Frees a range of blocks.
     pub(super) fn free_blocks(&self, range: Range<Ext2Bid>) -> Result<()> {
+        if let Some(journal) = self.journal.as_ref() {
+            journal.forget(range.clone());
+        }
+
         let mut current_range = range.clone();
         while !current_range.is_empty() {
             let (_, block_group) = self.block_group_of_bid(current_range.start)?;
@@ -308,6 +460,10 @@ impl Ext2 {
             current_range.start += range_in_group.len() as Ext2Bid
         }
 
+        if let Some(pending_discards) = self.pending_discards.as_ref() {
+            pending_discards.lock().push(range);
+        }
+
         Ok(())
     }
 
@@ -357,13 +513,243 @@ impl Ext2 {
         Ok(waiter)
     }
 
+    /// Reads contiguous metadata blocks starting from the `bid` asynchronously.
+    ///
+    /// The blocks modified in the running transaction of the journal
+    /// are read from the transaction.
+    pub(super) fn read_metadata_blocks_async(
+        &self,
+        bid: Ext2Bid,
+        segment: USegment,
+    ) -> Result<BioWaiter> {
+        let nblocks = (segment.size() / BLOCK_SIZE) as Ext2Bid;
+        let Some(journal) = self
+            .journal
+            .as_ref()
+            .filter(|journal| journal.contains_any(bid..bid + nblocks))
+        else {
+            let bio_segment = BioSegment::new_from_segment(segment, BioDirection::FromDevice);
+            return self.read_blocks_async(bid, bio_segment);
+        };
+
+        let mut buf = vec![0u8; BLOCK_SIZE];
+        for idx in 0..nblocks {
+            self.read_latest_block(journal, bid + idx, &mut buf)?;
+            segment.write_bytes(idx as usize * BLOCK_SIZE, &buf)?;
+        }
+        Ok(BioWaiter::new())
+    }
+
+    /// Reads contiguous metadata blocks starting from the `bid` synchronously.
+    pub(super) fn read_metadata_blocks(&self, bid: Ext2Bid, segment: USegment) -> Result<()> {
+        match self.read_metadata_blocks_async(bid, segment)?.wait() {
+            Some(BioStatus::Complete) => Ok(()),
+            _ => return_errno_with_message!(Errno::EIO, "failed to read metadata blocks"),
+        }
+    }
+
+    /// Reads the metadata bytes at `offset` synchronously.
+    pub(super) fn read_metadata_bytes(&self, offset: usize, buf: &mut [u8]) -> Result<()> {
+        let Some(journal) = self.journal.as_ref() else {
+            self.block_device.read_bytes(offset, buf)?;
+            return Ok(());
+        };
+
+        let mut block = vec![0u8; BLOCK_SIZE];
+        let mut pos = 0;
+        while pos < buf.len() {
+            let bid = ((offset + pos) / BLOCK_SIZE) as Ext2Bid;
+            let offset_in_block = (offset + pos) % BLOCK_SIZE;
+            let len = (BLOCK_SIZE - offset_in_block).min(buf.len() - pos);
+            self.read_latest_block(journal, bid, &mut block)?;
+            buf[pos..pos + len].copy_from_slice(&block[offset_in_block..offset_in_block + len]);
+            pos += len;
+        }
+        Ok(())
+    }
+
+    /// Writes contiguous metadata blocks starting from the `bid` asynchronously.
+    ///
+    /// If the filesystem has a journal, the blocks are written to the running transaction,
+    /// and reach the device when the transaction is committed.
+    pub(super) fn write_metadata_blocks_async(
+        &self,
+        bid: Ext2Bid,
+        segment: &USegment,
+    ) -> Result<BioWaiter> {
+        let nblocks = segment.size() / BLOCK_SIZE;
+        let Some(journal) = self.journal.as_ref() else {
+            let bio_segment = BioSegment::alloc(nblocks, BioDirection::ToDevice);
+            // This requires an additional copy to the pooled bio segment.
+            bio_segment
+                .writer()
+                .unwrap()
+                .write_fallible(&mut segment.reader().to_fallible())?;
+            return self.write_blocks_async(bid, bio_segment);
+        };
+
+        let mut buf = vec![0u8; BLOCK_SIZE];
+        for idx in 0..nblocks {
+            segment.read_bytes(idx * BLOCK_SIZE, &mut buf)?;
+            journal.write_block(bid + idx as Ext2Bid, &buf);
+        }
+        Ok(BioWaiter::new())
+    }
+
+    /// Writes the metadata bytes at `offset` asynchronously.
+    ///
+    /// If the filesystem has a journal, the bytes are written to the running transaction,
+    /// and reach the device when the transaction is committed.
+    pub(super) fn write_metadata_bytes_async(
+        &self,
+        offset: usize,
+        buf: &[u8],
+    ) -> Result<BioWaiter> {
+        let Some(journal) = self.journal.as_ref() else {
+            let bio_waiter = self.block_device.write_bytes_async(offset, buf)?;
+            return Ok(bio_waiter);
+        };
+
+        let mut block = vec![0u8; BLOCK_SIZE];
+        let mut pos = 0;
+        while pos < buf.len() {
+            let bid = ((offset + pos) / BLOCK_SIZE) as Ext2Bid;
+            let offset_in_block = (offset + pos) % BLOCK_SIZE;
+            let len = (BLOCK_SIZE - offset_in_block).min(buf.len() - pos);
+            if len < BLOCK_SIZE {
+                // The rest of the block is kept.
+                self.read_latest_block(journal, bid, &mut block)?;
+            }
+            block[offset_in_block..offset_in_block + len].copy_from_slice(&buf[pos..pos + len]);
+            journal.write_block(bid, &block);
+            pos += len;
+        }
+        Ok(BioWaiter::new())
+    }
+
+    /// Reads the latest content of a block, which is either in the running transaction
+    /// of the journal or on the device.
+    fn read_latest_block(&self, journal: &Journal, bid: Ext2Bid, buf: &mut [u8]) -> Result<()> {
+        if !journal.read_block(bid, buf) {
+            self.block_device
+                .read_bytes(bid as usize * BLOCK_SIZE, buf)?;
+        }
+        Ok(())
+    }
+
     /// Writes back the metadata to the block device.
+    ///
+    /// If the filesystem has a journal, the metadata are committed with the running
+    /// transaction.
     pub fn sync_metadata(&self) -> Result<()> {
         // If the superblock is clean, the block groups must be clean.
-        if !self.super_block.read().is_dirty() {
+        if self.super_block.read().is_dirty() {
+            self.sync_super_block_and_groups()?;
+        }
+
+        if let Some(journal) = self.journal.as_ref() {
+            journal.commit(self.block_device.as_ref())?;
+        }
+
+        self.discard_freed_blocks();
+        Ok(())
+    }
+
+    /// Discards the blocks freed before the metadata are synced.
+    ///
+    /// The blocks are not discarded earlier, or the data of the deleted files could not be
+    /// recovered with the old metadata after a crash.
+    fn discard_freed_blocks(&self) {
+        let Some(pending_discards) = self.pending_discards.as_ref() else {
+            return;
+        };
+
+        // Holding the lock prevents the reallocated blocks from being written before they are
+        // discarded.
+        let mut pending_discards = pending_discards.lock();
+        for range in pending_discards.drain(..) {
+            // Discarding is only a hint, so the failures are not fatal.
+            if let Err(err) = self.discard_blocks(range.clone()) {
+                warn!("failed to discard the blocks {:?}: {:?}", range, err);
+            }
+        }
+    }
+
+    /// Discards the free blocks in the byte range of `start..start + len` on the device,
+    /// skipping the free extents shorter than `min_len` bytes.
+    ///
+    /// Returns the number of discarded bytes.
+    pub(super) fn trim(&self, start: u64, len: u64, min_len: u64) -> Result<u64> {
+        if self.block_device.metadata().max_discard_sectors == 0 {
+            return_errno_with_message!(Errno::EOPNOTSUPP, "the block device cannot discard");
+        }
+
+        let block_size = self.block_size as u64;
+        let total_blocks = self.super_block.read().total_blocks() as u64;
+        let start_bid = start / block_size;
+        let end_bid = start_bid.saturating_add(len / block_size).min(total_blocks);
+        let min_blocks = min_len.div_ceil(block_size).max(1);
+        if start_bid >= total_blocks
+            || len < block_size
+            || min_blocks > self.blocks_per_group as u64
+        {
+            return_errno_with_message!(Errno::EINVAL, "the trim range is invalid");
+        }
+
+        let mut nr_trimmed = 0;
+        let mut bid = start_bid as Ext2Bid;
+        while bid < end_bid as Ext2Bid {
+            let (block_group_idx, block_group) = self.block_group_of_bid(bid)?;
+            let group_start = block_group_idx as Ext2Bid * self.blocks_per_group;
+            let group_end = (group_start + self.blocks_per_group).min(end_bid as Ext2Bid);
+
+            nr_trimmed += block_group.trim_free_blocks(
+                (bid - group_start)..(group_end - group_start),
+                min_blocks as Ext2Bid,
+                |range| self.discard_blocks((range.start + group_start)..(range.end + group_start)),
+            )?;
+            bid = group_end;
+        }
+
+        Ok(nr_trimmed as u64 * block_size)
+    }
+
+    /// Discards a range of blocks on the block device.
+    fn discard_blocks(&self, range: Range<Ext2Bid>) -> Result<()> {
+        let start = Sid::from(Bid::new(range.start as u64));
+        let end = Sid::from(Bid::new(range.end as u64));
+        match self.block_device.discard(start..end)? {
+            BioStatus::Complete => Ok(()),
+            err_status => Err(Error::from(err_status)),
+        }
+    }
+
+    /// Commits the metadata modified so far to the journal.
+    ///
+    /// Like the ordered mode of Linux, the data of all the inodes are written back
+    /// before the metadata are committed. If the filesystem has no journal,
+    /// this method does nothing.
+    pub(super) fn commit_journal(&self) -> Result<()> {
+        if self.journal.is_none() {
             return Ok(());
         }
 
+        self.sync_all_inodes()?;
+        self.sync_metadata()
+    }
+
+    /// Flushes the volatile write cache of the block device.
+    ///
+    /// After this method succeeds, all the completed writes survive a power loss.
+    pub(super) fn flush_block_device(&self) -> Result<()> {
+        match self.block_device.sync()? {
+            BioStatus::Complete => Ok(()),
+            err_status => Err(Error::from(err_status)),
+        }
+    }
+
+    /// Writes back the superblock and the metadata of block groups.
+    fn sync_super_block_and_groups(&self) -> Result<()> {
         let mut super_block = self.super_block.write();
         // Writes back the metadata of block groups
         for block_group in &self.block_groups {
@@ -374,16 +760,11 @@ impl Ext2 {
         let mut bio_waiter = BioWaiter::new();
         let raw_super_block = RawSuperBlock::from((*super_block).deref());
         bio_waiter.concat(
-            self.block_device
-                .write_bytes_async(SUPER_BLOCK_OFFSET, raw_super_block.as_bytes())?,
+            self.write_metadata_bytes_async(SUPER_BLOCK_OFFSET, raw_super_block.as_bytes())?,
         );
-        let group_descriptors_bio_segment = BioSegment::new_from_segment(
-            self.group_descriptors_segment.clone(),
-            BioDirection::ToDevice,
-        );
-        bio_waiter.concat(self.block_device.write_blocks_async(
-            super_block.group_descriptors_bid(0),
-            group_descriptors_bio_segment.clone(),
+        bio_waiter.concat(self.write_metadata_blocks_async(
+            super_block.group_descriptors_bid(0).to_raw() as Ext2Bid,
+            &self.group_descriptors_segment,
         )?);
         bio_waiter
             .wait()
@@ -391,11 +772,18 @@ impl Ext2 {
         drop(bio_waiter);
 
         // Writes back the backups of superblock and group descriptor table.
+        // The backups are not journaled, since they are used only if the main
+        // metadata are corrupted.
+        let group_descriptors_bio_segment = BioSegment::new_from_segment(
+            self.group_descriptors_segment.clone(),
+            BioDirection::ToDevice,
+        );
         let mut raw_super_block_backup = raw_super_block;
         for idx in 1..super_block.block_groups_count() {
             if super_block.is_backup_group(idx as usize) {
                 let mut bio_waiter = BioWaiter::new();
                 raw_super_block_backup.block_group_idx = idx as u16;
+                raw_super_block_backup.update_checksum();
                 bio_waiter.concat(self.block_device.write_bytes_async(
                     super_block.bid(idx as usize).to_offset(),
                     raw_super_block_backup.as_bytes(),
@@ -450,6 +838,82 @@ impl Ext2 {
     pub fn fs_event_subscriber_stats(&self) -> &FsEventSubscriberStats {
         &self.fs_event_subscriber_stats
     }
+
+    /// Returns the disk quotas.
+    pub fn disk_quotas(&self) -> &DiskQuotas {
+        &self.disk_quotas
+    }
+
+    /// Returns the keyring of the master keys for file encryption.
+    pub fn fscrypt_keyring(&self) -> &FscryptKeyring {
+        &self.fscrypt_keyring
+    }
+
+    /// Marks that encrypted inodes are present in the filesystem.
+    pub(super) fn enable_encryption(&self) {
+        if self
+            .super_block
+            .read()
+            .feature_incompat()
+            .contains(FeatureInCompatSet::ENCRYPT)
+        {
+            return;
+        }
+        self.super_block.write().set_encrypt();
+    }
+}
+
+/// Removes the `range` from the sorted or unsorted `ranges`.
+fn remove_range(ranges: &mut Vec<Range<Ext2Bid>>, range: &Range<Ext2Bid>) {
+    if !ranges
+        .iter()
+        .any(|r| r.start < range.end && range.start < r.end)
+    {
+        return;
+    }
+
+    let old_ranges = core::mem::take(ranges);
+    for r in old_ranges {
+        if r.end <= range.start || range.end <= r.start {
+            ranges.push(r);
+            continue;
+        }
+        if r.start < range.start {
+            ranges.push(r.start..range.start);
+        }
+        if range.end < r.end {
+            ranges.push(range.end..r.end);
+        }
+    }
+}
+
+/// The mount options of an Ext2 filesystem.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Ext2MountOptions {
+    /// Whether the freed blocks are discarded, which is also known as the online discard.
+    pub(super) discard: bool,
+}
+
+impl Ext2MountOptions {
+    fn parse(args: Option<&CStr>) -> Self {
+        let mut options = Self::default();
+
+        let Some(args) = args else {
+            return options;
+        };
+        let args = args.to_string_lossy();
+
+        for entry in args.split(',').filter(|entry| !entry.is_empty()) {
+            match entry {
+                "discard" => options.discard = true,
+                "nodiscard" => options.discard = false,
+                // TODO: Support more options. The unknown ones are ignored for now.
+                _ => {}
+            }
+        }
+
+        options
+    }
 }
 
 pub(super) struct Ext2Type;
@@ -466,10 +930,43 @@ impl FsType for Ext2Type {
     fn create(
         &self,
         _flags: FsFlags,
-        _args: Option<CString>,
+        _source: Option<&str>,
+        args: Option<CString>,
+        disk: Option<Arc<dyn BlockDevice>>,
+    ) -> Result<Arc<dyn FileSystem>> {
+        let options = Ext2MountOptions::parse(args.as_deref());
+        Ext2::open_as(disk.unwrap(), "ext2", options).map(|fs| fs as _)
+    }
+
+    fn sysnode(&self) -> Option<Arc<dyn aster_systree::SysNode>> {
+        None
+    }
+}
+
+/// The Ext4 filesystem type.
+///
+/// Ext4 filesystems are served by the same driver as Ext2 ones.
+/// The driver refuses to mount an Ext4 filesystem with unsupported features.
+pub(super) struct Ext4Type;
+
+impl FsType for Ext4Type {
+    fn name(&self) -> &'static str {
+        "ext4"
+    }
+
+    fn properties(&self) -> FsProperties {
+        FsProperties::NEED_DISK
+    }
+
+    fn create(
+        &self,
+        _flags: FsFlags,
+        _source: Option<&str>,
+        args: Option<CString>,
         disk: Option<Arc<dyn BlockDevice>>,
     ) -> Result<Arc<dyn FileSystem>> {
-        Ext2::open(disk.unwrap()).map(|fs| fs as _)
+        let options = Ext2MountOptions::parse(args.as_deref());
+        Ext2::open_as(disk.unwrap(), "ext4", options).map(|fs| fs as _)
     }
 
     fn sysnode(&self) -> Option<Arc<dyn aster_systree::SysNode>> {
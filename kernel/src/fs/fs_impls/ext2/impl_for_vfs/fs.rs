@@ -6,7 +6,9 @@ use crate::{
         utils::NAME_MAX,
         vfs::{
             file_system::{FileSystem, FsEventSubscriberStats, SuperBlock},
+            fscrypt::FscryptKeyring,
             inode::Inode,
+            quota::DiskQuotas,
         },
     },
     prelude::*,
@@ -14,15 +16,15 @@ use crate::{
 
 impl FileSystem for Ext2 {
     fn name(&self) -> &'static str {
-        "ext2"
+        self.fs_type_name()
     }
 
     fn sync(&self) -> Result<()> {
+        self.disk_quotas().sync()?;
         self.sync_all_inodes()?;
         self.sync_metadata()?;
 
-        self.block_device().sync()?;
-        Ok(())
+        self.flush_block_device()
     }
 
     fn root_inode(&self) -> Arc<dyn Inode> {
@@ -50,4 +52,16 @@ impl FileSystem for Ext2 {
     fn fs_event_subscriber_stats(&self) -> &FsEventSubscriberStats {
         self.fs_event_subscriber_stats()
     }
+
+    fn disk_quotas(&self) -> Option<&DiskQuotas> {
+        Some(self.disk_quotas())
+    }
+
+    fn fscrypt_keyring(&self) -> Option<&FscryptKeyring> {
+        Some(self.fscrypt_keyring())
+    }
+
+    fn trim(&self, start: u64, len: u64, min_len: u64) -> Result<u64> {
+        self.trim(start, len, min_len)
+    }
 }
@@ -12,7 +12,9 @@ use crate::{
         utils::DirentVisitor,
         vfs::{
             file_system::FileSystem,
+            fscrypt::{FscryptContext, FscryptPolicy},
             inode::{Extension, FallocMode, Inode, InodeIo, Metadata, MknodType, SymbolicLink},
+            posix_acl::{PosixAcl, PosixAclType},
             xattr::{XattrName, XattrNamespace, XattrSetFlags},
         },
     },
@@ -99,8 +101,7 @@ impl Inode for Ext2Inode {
     }
 
     fn set_mode(&self, mode: InodeMode) -> Result<()> {
-        self.set_file_perm(mode.into());
-        Ok(())
+        self.set_file_perm(mode.into())
     }
 
     fn owner(&self) -> Result<Uid> {
@@ -108,8 +109,7 @@ impl Inode for Ext2Inode {
     }
 
     fn set_owner(&self, uid: Uid) -> Result<()> {
-        self.set_uid(uid.into());
-        Ok(())
+        self.set_uid(uid.into())
     }
 
     fn group(&self) -> Result<Gid> {
@@ -117,8 +117,7 @@ impl Inode for Ext2Inode {
     }
 
     fn set_group(&self, gid: Gid) -> Result<()> {
-        self.set_gid(gid.into());
-        Ok(())
+        self.set_gid(gid.into())
     }
 
     fn page_cache(&self) -> Option<Arc<Vmo>> {
@@ -154,6 +153,13 @@ impl Inode for Ext2Inode {
 
                 Some(pipe.open_named(access_mode, status_flags))
             }
+            InodeType::File => {
+                // The contents of an encrypted file cannot be accessed without the key.
+                if let Err(err) = self.check_fscrypt_key() {
+                    return Some(Err(err));
+                }
+                None
+            }
             _ => None,
         }
     }
@@ -224,14 +230,14 @@ impl Inode for Ext2Inode {
 
     fn sync_all(&self) -> Result<()> {
         self.sync_all()?;
-        self.fs().block_device().sync()?;
-        Ok(())
+        self.fs().commit_journal()?;
+        self.fs().flush_block_device()
     }
 
     fn sync_data(&self) -> Result<()> {
         self.sync_data()?;
-        self.fs().block_device().sync()?;
-        Ok(())
+        self.fs().commit_journal()?;
+        self.fs().flush_block_device()
     }
 
     fn fs(&self) -> Arc<dyn FileSystem> {
@@ -262,6 +268,23 @@ impl Inode for Ext2Inode {
     fn remove_xattr(&self, name: XattrName) -> Result<()> {
         self.remove_xattr(name)
     }
+
+    fn posix_acl(&self, type_: PosixAclType) -> Result<Option<PosixAcl>> {
+        self.posix_acl(type_)
+    }
+
+    fn fscrypt_context(&self) -> Option<FscryptContext> {
+        self.fscrypt_context()
+    }
+
+    fn set_fscrypt_policy(&self, policy: &FscryptPolicy) -> Result<()> {
+        self.set_fscrypt_policy(policy)
+    }
+
+    fn is_dentry_cacheable(&self) -> bool {
+        // The names in an encrypted directory change after the key is added or removed.
+        self.fscrypt_context().is_none()
+    }
 }
 
 impl From<FilePerm> for InodeMode {
@@ -43,11 +43,7 @@ impl IndirectBlockCache {
         let fs = self.fs();
         let load_block = || -> Result<IndirectBlock> {
             let mut block = IndirectBlock::alloc_uninit()?;
-            let bio_segment = BioSegment::new_from_segment(
-                Segment::<()>::from(block.frame.clone()).into(),
-                BioDirection::FromDevice,
-            );
-            fs.read_blocks(bid, bio_segment)?;
+            fs.read_metadata_blocks(bid, Segment::<()>::from(block.frame.clone()).into())?;
             block.state = State::UpToDate;
             Ok(block)
         };
@@ -64,11 +60,7 @@ impl IndirectBlockCache {
         let fs = self.fs();
         let load_block = || -> Result<IndirectBlock> {
             let mut block = IndirectBlock::alloc_uninit()?;
-            let bio_segment = BioSegment::new_from_segment(
-                Segment::<()>::from(block.frame.clone()).into(),
-                BioDirection::FromDevice,
-            );
-            fs.read_blocks(bid, bio_segment)?;
+            fs.read_metadata_blocks(bid, Segment::<()>::from(block.frame.clone()).into())?;
             block.state = State::UpToDate;
             Ok(block)
         };
@@ -113,11 +105,8 @@ impl IndirectBlockCache {
         for _ in 0..num {
             let (bid, block) = self.cache.pop_lru().unwrap();
             if block.is_dirty() {
-                let bio_segment = BioSegment::new_from_segment(
-                    Segment::<()>::from(block.frame.clone()).into(),
-                    BioDirection::ToDevice,
-                );
-                bio_waiter.concat(self.fs().write_blocks_async(bid, bio_segment)?);
+                let segment = Segment::<()>::from(block.frame.clone()).into();
+                bio_waiter.concat(self.fs().write_metadata_blocks_async(bid, &segment)?);
             }
         }
 
@@ -3,16 +3,22 @@
 #![expect(dead_code)]
 #![expect(unused_variables)]
 
-use alloc::{borrow::ToOwned, rc::Rc};
+use alloc::{
+    borrow::{Cow, ToOwned},
+    rc::Rc,
+};
 use core::sync::atomic::{AtomicUsize, Ordering};
 
 use device_id::DeviceId;
 use inherit_methods_macro::inherit_methods;
 use ostd::{const_assert, mm::io::util::HasVmReaderWriter};
+use spin::Once;
 
 use super::{
     block_ptr::{BID_SIZE, BidPath, BlockPtrs, Ext2Bid, MAX_BLOCK_PTRS},
-    dir::{DirEntryHeader, DirEntryItem, DirEntryReader, DirEntryWriter},
+    checksum::crc32c,
+    dir::{DirEntryHeader, DirEntryItem, DirEntryReader, DirEntryWriter, set_tail_checksum},
+    extent::{ExtentMapping, ExtentTree},
     fs::Ext2,
     indirect_block_cache::{IndirectBlock, IndirectBlockCache},
     prelude::*,
@@ -24,12 +30,16 @@ use crate::{
         file::{InodeMode, Permission},
         pipe::Pipe,
         vfs::{
+            fscrypt::{FscryptContext, FscryptFileKey, FscryptPolicy, NoKeyName},
             inode::{Extension, FallocMode, Inode as _, Metadata},
             path::{is_dot, is_dot_or_dotdot, is_dotdot},
+            posix_acl::{PosixAcl, PosixAclType},
+            quota::QuotaIds,
             xattr::{XattrName, XattrNamespace, XattrSetFlags},
         },
     },
-    process::{Gid, Uid, posix_thread::AsPosixThread},
+    process::{Gid, Uid, credentials::capabilities::CapSet, posix_thread::AsPosixThread},
+    util::random::getrandom,
 };
 
 /// Max length of file name.
@@ -57,8 +67,12 @@ impl Inode {
         ino: u32,
         block_group_idx: usize,
         desc: Dirty<InodeDesc>,
+        extent_tree: Option<ExtentTree>,
         fs: Weak<Ext2>,
     ) -> Arc<Self> {
+        let csum_seed = fs
+            .upgrade()
+            .and_then(|fs| fs.inode_csum_seed(ino, desc.generation));
         Arc::new_cyclic(|weak_self| Self {
             ino,
             type_: desc.type_,
@@ -74,7 +88,13 @@ impl Inode {
                 }
                 _ => None,
             },
-            inner: RwMutex::new(InodeInner::new(desc, weak_self.clone(), fs.clone())),
+            inner: RwMutex::new(InodeInner::new(
+                desc,
+                extent_tree,
+                csum_seed,
+                weak_self.clone(),
+                fs.clone(),
+            )),
             fs,
             extension: Extension::new(),
         })
@@ -136,6 +156,7 @@ impl Inode {
         if self.type_ != InodeType::File {
             return_errno!(Errno::EINVAL);
         }
+        self.check_fscrypt_key()?;
 
         let inner = self.inner.upread();
         if new_size == inner.file_size() {
@@ -170,17 +191,36 @@ impl Inode {
             return_errno_with_message!(Errno::ENOENT, "dir removed");
         }
 
+        let fscrypt_context = inner.fscrypt_context();
+        let name = inner.disk_name(name, true)?;
+        if fscrypt_context.is_some() && inode_type == InodeType::SymLink {
+            return_errno_with_message!(
+                Errno::EOPNOTSUPP,
+                "symbolic links cannot be created in encrypted directories"
+            );
+        }
+
         let inode = self
             .fs()
             .create_inode(self.block_group_idx, inode_type, file_perm)?;
         let is_dir = inode_type == InodeType::Dir;
-        if let Err(e) = inode.init(self.ino) {
+        if let Err(e) = inode.inner.read().charge_new_inode() {
+            self.fs().free_inode(inode.ino, is_dir).unwrap();
+            return Err(e);
+        }
+        if let Err(e) = inode
+            .init(self.ino)
+            .and_then(|_| inode.inherit_posix_acl(self))
+            .and_then(|_| inode.inherit_fscrypt_context(fscrypt_context.as_ref()))
+        {
+            inode.inner.read().release_new_inode();
             self.fs().free_inode(inode.ino, is_dir).unwrap();
             return Err(e);
         }
 
         let mut inner = inner.upgrade();
-        if let Err(e) = inner.append_new_entry(inode.ino, inode_type, name, true) {
+        if let Err(e) = inner.append_new_entry(inode.ino, inode_type, &name, true) {
+            inode.inner.read().release_new_inode();
             self.fs().free_inode(inode.ino, is_dir).unwrap();
             return Err(e);
         }
@@ -204,6 +244,49 @@ impl Inode {
         Ok(())
     }
 
+    /// Inherits the default ACL of the parent directory `dir`.
+    ///
+    /// The new inode gets an access ACL derived from the default ACL, and its permission
+    /// bits are restricted by the default ACL. A new directory also inherits the default
+    /// ACL itself.
+    //
+    // TODO: Linux does not apply the umask if the parent directory has a default ACL,
+    // but the umask has been applied before the inode is created here.
+    fn inherit_posix_acl(&self, dir: &Inode) -> Result<()> {
+        if self.type_ == InodeType::SymLink {
+            return Ok(());
+        }
+        let Some(default_acl) = dir.posix_acl(PosixAclType::Default)? else {
+            return Ok(());
+        };
+
+        if self.type_ == InodeType::Dir {
+            self.write_posix_acl(PosixAclType::Default, Some(&default_acl))?;
+        }
+
+        let mut access_acl = default_acl;
+        let mode = access_acl.create_masq(InodeMode::from(self.file_perm()));
+        self.inner.write().set_file_perm(mode.into());
+        if !access_acl.is_minimal() && self.xattr.is_some() {
+            self.write_posix_acl(PosixAclType::Access, Some(&access_acl))?;
+        }
+        Ok(())
+    }
+
+    /// Inherits the encryption policy of the parent directory, whose encryption context
+    /// is `dir_context`.
+    ///
+    /// Only the regular files and the directories are encrypted.
+    fn inherit_fscrypt_context(&self, dir_context: Option<&FscryptContext>) -> Result<()> {
+        let Some(dir_context) = dir_context else {
+            return Ok(());
+        };
+        if !matches!(self.type_, InodeType::File | InodeType::Dir) {
+            return Ok(());
+        }
+        self.set_fscrypt_context(dir_context.inherit())
+    }
+
     pub fn lookup(&self, name: &str) -> Result<Arc<Self>> {
         if name.len() > MAX_FNAME_LEN {
             return_errno!(Errno::ENAMETOOLONG);
@@ -217,8 +300,9 @@ impl Inode {
             return_errno_with_message!(Errno::ENOENT, "dir removed");
         }
 
+        let name = inner.disk_name(name, false)?;
         let ino = inner
-            .find_entry_item(name)
+            .find_entry_item(&name)
             .map(|entry| entry.ino())
             .ok_or(Error::new(Errno::ENOENT))?;
         drop(inner);
@@ -243,8 +327,11 @@ impl Inode {
             return_errno!(Errno::EPERM);
         }
 
+        let name = inner.disk_name(name, true)?;
+        check_fscrypt_permitted(inner.fscrypt_context().as_ref(), inode)?;
+
         let mut inner = inner.upgrade();
-        inner.append_new_entry(inode.ino, inode_type, name, true)?;
+        inner.append_new_entry(inode.ino, inode_type, &name, true)?;
         let now = now();
         inner.set_mtime(now);
         inner.set_ctime(now);
@@ -266,6 +353,7 @@ impl Inode {
         if file.inode_type() == InodeType::Dir {
             return_errno!(Errno::EISDIR);
         }
+        let name = self.inner.read().disk_name(name, false)?;
 
         let (mut self_inner, mut file_inner) = write_lock_two_inodes(self, &file);
         // When we got the lock, the dir may have been modified by another thread
@@ -273,7 +361,7 @@ impl Inode {
             return_errno_with_message!(Errno::ENOENT, "dir removed");
         }
         let (offset, new_ino) = self_inner
-            .find_entry_item(name)
+            .find_entry_item(&name)
             .map(|entry| (entry.offset(), entry.ino()))
             .ok_or(Error::new(Errno::ENOENT))?;
         if file.ino != new_ino {
@@ -284,7 +372,7 @@ impl Inode {
             return_errno!(Errno::ENOENT);
         }
 
-        self_inner.remove_entry_at(name, offset)?;
+        self_inner.remove_entry_at(&name, offset)?;
         file_inner.dec_hard_links();
         let now = now();
         self_inner.set_mtime(now);
@@ -311,6 +399,7 @@ impl Inode {
             return_errno!(Errno::ENOTEMPTY);
         }
         drop(dir_inner);
+        let name = self.inner.read().disk_name(name, false)?;
 
         let (mut self_inner, mut dir_inner) = write_lock_two_inodes(self, &dir_inode);
         // When we got the lock, the dir may have been modified by another thread
@@ -318,7 +407,7 @@ impl Inode {
             return_errno_with_message!(Errno::ENOENT, "dir removed");
         }
         let (offset, new_ino) = self_inner
-            .find_entry_item(name)
+            .find_entry_item(&name)
             .map(|entry| (entry.offset(), entry.ino()))
             .ok_or(Error::new(Errno::ENOENT))?;
         if dir_inode.ino != new_ino {
@@ -335,7 +424,7 @@ impl Inode {
             return_errno!(Errno::ENOTEMPTY);
         }
 
-        self_inner.remove_entry_at(name, offset)?;
+        self_inner.remove_entry_at(&name, offset)?;
         let now = now();
         self_inner.set_mtime(now);
         self_inner.set_ctime(now);
@@ -346,7 +435,7 @@ impl Inode {
     }
 
     /// Rename within its own directory.
-    fn rename_within(&self, old_name: &str, new_name: &str) -> Result<()> {
+    fn rename_within(&self, old_name: &[u8], new_name: &[u8]) -> Result<()> {
         if self.type_ != InodeType::Dir {
             return_errno!(Errno::ENOTDIR);
         }
@@ -458,6 +547,12 @@ impl Inode {
             return_errno!(Errno::ENAMETOOLONG);
         }
 
+        // Renaming in an encrypted directory requires the key.
+        let old_name = self.inner.read().disk_name(old_name, true)?;
+        let old_name = old_name.as_ref();
+        let new_name = target.inner.read().disk_name(new_name, true)?;
+        let new_name = new_name.as_ref();
+
         // Rename inside the inode
         if self.ino == target.ino {
             return self.rename_within(old_name, new_name);
@@ -483,6 +578,7 @@ impl Inode {
         if src_inode.ino == target.ino {
             return_errno!(Errno::EINVAL);
         }
+        check_fscrypt_permitted(target_inner.fscrypt_context().as_ref(), &src_inode)?;
         let is_dir = src_inode_typ == InodeType::Dir;
 
         let Some(dst_ino) = target_inner
@@ -639,16 +735,38 @@ impl Inode {
                 return_errno_with_message!(Errno::ENOENT, "dir removed");
             }
 
+            // Without the key, the encrypted names are shown as no-key names.
+            let file_key = inner
+                .fscrypt_context()
+                .map(|context| self.fs().fscrypt_keyring().file_key(&context).ok());
+
             let try_readdir = |offset: &mut usize, visitor: &mut dyn DirentVisitor| -> Result<()> {
                 let mut dir_entry_reader = DirEntryReader::new(&inner.page_cache, *offset);
-                for dir_entry in dir_entry_reader.iter_entries() {
+                for (entry_offset, dir_entry) in dir_entry_reader.iter_entries() {
+                    let name = match &file_key {
+                        Some(file_key) if !matches!(dir_entry.name(), b"." | b"..") => {
+                            match file_key {
+                                Some(file_key) => {
+                                    Cow::Owned(file_key.decrypt_name(dir_entry.name())?)
+                                }
+                                None => Cow::Owned(
+                                    NoKeyName::new(dir_entry.name()).encode().into_bytes(),
+                                ),
+                            }
+                        }
+                        _ => Cow::Borrowed(dir_entry.name()),
+                    };
+                    let name = core::str::from_utf8(&name).map_err(|_| {
+                        Error::with_message(Errno::EUCLEAN, "the name is not valid UTF-8")
+                    })?;
                     visitor.visit(
-                        dir_entry.name(),
+                        name,
                         dir_entry.ino() as u64,
                         dir_entry.type_(),
                         dir_entry.record_len(),
                     )?;
-                    *offset += dir_entry.record_len();
+                    // The unused entries are skipped, so the offset is not accumulated.
+                    *offset = entry_offset + dir_entry.record_len();
                 }
 
                 Ok(())
@@ -723,6 +841,10 @@ impl Inode {
         if !is_block_aligned(offset) || !is_block_aligned(writer.avail()) {
             return_errno_with_message!(Errno::EINVAL, "not block-aligned");
         }
+        if self.fscrypt_context().is_some() {
+            // The contents are decrypted in the page cache.
+            return self.read_at(offset, writer);
+        }
 
         let bytes_read = self.inner.read().read_direct_at(offset, writer)?;
 
@@ -764,6 +886,10 @@ impl Inode {
         if !is_block_aligned(offset) || !is_block_aligned(reader.remain()) {
             return_errno_with_message!(Errno::EINVAL, "not block aligned");
         }
+        if self.fscrypt_context().is_some() {
+            // The contents are encrypted when the page cache is written back.
+            return self.write_at(offset, reader);
+        }
 
         let mut inner = self.inner.write();
         let bytes_written = inner.write_direct_at(offset, reader)?;
@@ -785,22 +911,32 @@ impl Inode {
         Ok(())
     }
 
-    pub fn set_file_perm(&self, perm: FilePerm) {
+    pub fn set_file_perm(&self, perm: FilePerm) -> Result<()> {
         let mut inner = self.inner.write();
         inner.set_file_perm(perm);
         inner.set_ctime(now());
+        drop(inner);
+
+        // Keeps the access ACL consistent with the new permission bits.
+        if let Some(mut acl) = self.posix_acl(PosixAclType::Access)? {
+            acl.chmod(InodeMode::from(perm));
+            self.write_posix_acl(PosixAclType::Access, Some(&acl))?;
+        }
+        Ok(())
     }
 
-    pub fn set_uid(&self, uid: u32) {
+    pub fn set_uid(&self, uid: u32) -> Result<()> {
         let mut inner = self.inner.write();
-        inner.set_uid(uid);
+        inner.set_uid(uid)?;
         inner.set_ctime(now());
+        Ok(())
     }
 
-    pub fn set_gid(&self, gid: u32) {
+    pub fn set_gid(&self, gid: u32) -> Result<()> {
         let mut inner = self.inner.write();
-        inner.set_gid(gid);
+        inner.set_gid(gid)?;
         inner.set_ctime(now());
+        Ok(())
     }
 
     pub fn extension(&self) -> &Extension {
@@ -852,6 +988,21 @@ impl Inode {
             Errno::EPERM,
             "xattr is not supported on the file type",
         ))?;
+
+        if let Some(acl_type) = PosixAclType::from_xattr_name(&name) {
+            self.check_acl_owner()?;
+            let value = {
+                let mut value = vec![0u8; value_reader.remain()];
+                value_reader.read_fallible(&mut VmWriter::from(value.as_mut_slice()))?;
+                value
+            };
+            let acl = PosixAcl::from_xattr_value(&value)?;
+            return self.set_posix_acl(acl_type, acl);
+        }
+        if name.namespace() == XattrNamespace::System {
+            return_errno_with_message!(Errno::EOPNOTSUPP, "unsupported system xattr");
+        }
+
         self.check_permission(Permission::MAY_WRITE)?;
         xattr.set(name, value_reader, flags)
     }
@@ -860,7 +1011,10 @@ impl Inode {
         if self.xattr.is_none() {
             return_errno_with_message!(Errno::ENODATA, "no available xattrs");
         }
-        self.check_permission(Permission::MAY_READ)?;
+        // The ACLs can be read without the read permission, like the permission bits.
+        if PosixAclType::from_xattr_name(&name).is_none() {
+            self.check_permission(Permission::MAY_READ)?;
+        }
         self.xattr.as_ref().unwrap().get(name, value_writer)
     }
 
@@ -880,8 +1034,168 @@ impl Inode {
             Errno::EPERM,
             "xattr is not supported on the file type",
         ))?;
+
+        if let Some(acl_type) = PosixAclType::from_xattr_name(&name) {
+            self.check_acl_owner()?;
+            if self.posix_acl(acl_type)?.is_none() {
+                return_errno_with_message!(Errno::ENODATA, "the target xattr does not exist");
+            }
+            return self.set_posix_acl(acl_type, None);
+        }
+
         self.check_permission(Permission::MAY_WRITE)?;
-        self.xattr.as_ref().unwrap().remove(name)
+        xattr.remove(name)
+    }
+
+    /// Returns the POSIX ACL of the given type.
+    pub fn posix_acl(&self, acl_type: PosixAclType) -> Result<Option<PosixAcl>> {
+        let Some(xattr) = self.xattr.as_ref() else {
+            return Ok(None);
+        };
+        let Some(value) = xattr.get_bytes(acl_type.xattr_name())? else {
+            return Ok(None);
+        };
+        PosixAcl::from_xattr_value(&value)
+    }
+
+    /// Sets or removes (if `acl` is `None`) the POSIX ACL of the given type.
+    ///
+    /// Setting an access ACL updates the permission bits accordingly. If the access ACL
+    /// is equivalent to the permission bits, it is not stored.
+    fn set_posix_acl(&self, acl_type: PosixAclType, acl: Option<PosixAcl>) -> Result<()> {
+        match acl_type {
+            PosixAclType::Access => {
+                let Some(acl) = acl else {
+                    return self.write_posix_acl(acl_type, None);
+                };
+
+                let mut inner = self.inner.write();
+                let mode = acl.equiv_mode(InodeMode::from(inner.file_perm()));
+                inner.set_file_perm(mode.into());
+                inner.set_ctime(now());
+                drop(inner);
+
+                let acl = (!acl.is_minimal()).then_some(&acl);
+                self.write_posix_acl(acl_type, acl)
+            }
+            PosixAclType::Default => {
+                if self.type_ != InodeType::Dir {
+                    if acl.is_some() {
+                        return_errno_with_message!(
+                            Errno::EACCES,
+                            "the default ACL can only be set on directories"
+                        );
+                    }
+                    return Ok(());
+                }
+                self.write_posix_acl(acl_type, acl.as_ref())
+            }
+        }
+    }
+
+    /// Writes the POSIX ACL of the given type to the xattr, or removes the xattr
+    /// if `acl` is `None`.
+    fn write_posix_acl(&self, acl_type: PosixAclType, acl: Option<&PosixAcl>) -> Result<()> {
+        let xattr = self.xattr.as_ref().ok_or(Error::with_message(
+            Errno::EOPNOTSUPP,
+            "ACLs are not supported on the file type",
+        ))?;
+
+        let Some(acl) = acl else {
+            return match xattr.remove(acl_type.xattr_name()) {
+                Err(e) if e.error() == Errno::ENODATA => Ok(()),
+                result => result,
+            };
+        };
+        let value = acl.to_xattr_value();
+        xattr.set(
+            acl_type.xattr_name(),
+            &mut VmReader::from(value.as_slice()).to_fallible(),
+            XattrSetFlags::CREATE_OR_REPLACE,
+        )
+    }
+
+    /// Returns the encryption context, or `None` if the inode is not encrypted.
+    pub fn fscrypt_context(&self) -> Option<FscryptContext> {
+        self.inner.read().fscrypt_context()
+    }
+
+    /// Sets the encryption policy of the directory, which must be empty.
+    pub fn set_fscrypt_policy(&self, policy: &FscryptPolicy) -> Result<()> {
+        if self.type_ != InodeType::Dir {
+            return_errno!(Errno::ENOTDIR);
+        }
+
+        let inner = self.inner.read();
+        if inner.hard_links() == 0 {
+            return_errno_with_message!(Errno::ENOENT, "dir removed");
+        }
+        if let Some(context) = inner.fscrypt_context() {
+            if context.policy() != *policy {
+                return_errno_with_message!(Errno::EEXIST, "the directory has a different policy");
+            }
+            return Ok(());
+        }
+        if inner.entry_count() > 2 {
+            return_errno_with_message!(Errno::ENOTEMPTY, "the directory is not empty");
+        }
+        drop(inner);
+
+        self.set_fscrypt_context(FscryptContext::new(policy))
+    }
+
+    /// Loads the encryption context of the inode that is loaded from the disk.
+    pub(super) fn load_fscrypt_context(&self) -> Result<()> {
+        if !self.file_flags().contains(FileFlags::ENCRYPT) {
+            return Ok(());
+        }
+        let Some(xattr) = self.xattr.as_ref() else {
+            // TODO: Support encrypted symbolic links.
+            return Ok(());
+        };
+
+        let Some(bytes) = xattr.encryption_context()? else {
+            return_errno_with_message!(Errno::EUCLEAN, "the encryption context is missing");
+        };
+        let context = FscryptContext::from_disk_bytes(&bytes)?;
+        self.inner.write().set_fscrypt_context(context);
+        Ok(())
+    }
+
+    /// Stores the encryption context and marks the inode as encrypted.
+    fn set_fscrypt_context(&self, context: FscryptContext) -> Result<()> {
+        let xattr = self.xattr.as_ref().unwrap();
+        xattr.set_encryption_context(context.as_bytes())?;
+
+        let mut inner = self.inner.write();
+        inner.set_fscrypt_context(context);
+        inner.insert_file_flags(FileFlags::ENCRYPT);
+        inner.set_ctime(now());
+        drop(inner);
+
+        self.fs().enable_encryption();
+        Ok(())
+    }
+
+    /// Checks whether the key of the encrypted file is present.
+    pub(super) fn check_fscrypt_key(&self) -> Result<()> {
+        let Some(context) = self.fscrypt_context() else {
+            return Ok(());
+        };
+        self.fs().fscrypt_keyring().file_key(&context)?;
+        Ok(())
+    }
+
+    /// Checks whether the current thread can change the ACLs,
+    /// i.e., whether it owns the inode or has the `CAP_FOWNER` capability.
+    fn check_acl_owner(&self) -> Result<()> {
+        let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+        if credentials.fsuid() != Uid::new(self.uid())
+            && !credentials.effective_capset().contains(CapSet::FOWNER)
+        {
+            return_errno_with_message!(Errno::EPERM, "only the owner can change the ACLs");
+        }
+        Ok(())
     }
 }
 
@@ -972,15 +1286,46 @@ fn write_lock_multiple_inodes(inodes: Vec<&Inode>) -> Vec<RwMutexWriteGuard<'_,
         .collect()
 }
 
+/// Checks whether `inode` can be linked to the directory with the encryption context
+/// `dir_context`.
+///
+/// In an encrypted directory, the regular files, the directories and the symbolic links
+/// must be encrypted with the same policy as the directory.
+fn check_fscrypt_permitted(dir_context: Option<&FscryptContext>, inode: &Inode) -> Result<()> {
+    let Some(dir_context) = dir_context else {
+        return Ok(());
+    };
+    if !matches!(
+        inode.type_,
+        InodeType::File | InodeType::Dir | InodeType::SymLink
+    ) {
+        return Ok(());
+    }
+
+    match inode.fscrypt_context() {
+        Some(context) if context.has_same_policy(dir_context) => Ok(()),
+        _ => return_errno_with_message!(
+            Errno::EXDEV,
+            "the file is not encrypted with the policy of the directory"
+        ),
+    }
+}
+
 struct InodeInner {
     inode_impl: InodeImpl,
     page_cache: PageCache,
 }
 
 impl InodeInner {
-    pub fn new(desc: Dirty<InodeDesc>, weak_self: Weak<Inode>, fs: Weak<Ext2>) -> Self {
+    pub fn new(
+        desc: Dirty<InodeDesc>,
+        extent_tree: Option<ExtentTree>,
+        csum_seed: Option<u32>,
+        weak_self: Weak<Inode>,
+        fs: Weak<Ext2>,
+    ) -> Self {
         let num_page_bytes = desc.num_page_bytes();
-        let inode_impl = InodeImpl::new(desc, weak_self, fs);
+        let inode_impl = InodeImpl::new(desc, extent_tree, csum_seed, weak_self, fs);
         Self {
             page_cache: PageCache::with_capacity(
                 num_page_bytes,
@@ -1043,7 +1388,12 @@ impl InodeInner {
         let new_size = offset + write_len;
         self.page_cache.resize(new_size.align_up(BLOCK_SIZE))?;
         self.page_cache.pages().write(offset, reader)?;
-        self.inode_impl.resize(new_size)?;
+        if let Err(err) = self.inode_impl.resize(new_size) {
+            // Drops the pages beyond the file, e.g., if the disk quotas are exceeded.
+            let file_size = self.inode_impl.file_size();
+            self.page_cache.resize(file_size.align_up(BLOCK_SIZE))?;
+            return Err(err);
+        }
         Ok(write_len)
     }
 
@@ -1099,16 +1449,79 @@ impl InodeInner {
 
     fn init_dir(&mut self, self_ino: u32, parent_ino: u32) -> Result<()> {
         debug_assert_eq!(self.inode_type(), InodeType::Dir);
-        DirEntryWriter::new(&self.page_cache, 0).init_dir(self_ino, parent_ino)?;
+        DirEntryWriter::new(&self.page_cache, 0, self.has_dir_tails())
+            .init_dir(self_ino, parent_ino)?;
+        self.inode_impl.resize(BLOCK_SIZE)?;
         self.inc_hard_links(); // for ".."
         Ok(())
     }
 
-    pub fn contains_entry(&self, name: &str) -> bool {
+    /// Returns whether each block of the directory ends with a checksum entry.
+    fn has_dir_tails(&self) -> bool {
+        self.inode_impl.block_manager.dir_csum_seed.is_some()
+    }
+
+    /// Converts a hash-indexed directory into a linear one.
+    ///
+    /// The hash tree is not maintained, so it is dropped before the first modification.
+    /// The index blocks look like unused entries to the linear lookup, so only their
+    /// checksums need fixing up.
+    fn strip_dir_index(&mut self) -> Result<()> {
+        if !self.file_flags().contains(FileFlags::INDEX_DIR) {
+            return Ok(());
+        }
+
+        if self.has_dir_tails() {
+            DirEntryWriter::new(&self.page_cache, 0, true).add_tails()?;
+        }
+        self.inode_impl.remove_file_flags(FileFlags::INDEX_DIR);
+        Ok(())
+    }
+
+    pub fn fscrypt_context(&self) -> Option<FscryptContext> {
+        self.inode_impl.block_manager.fscrypt_context.get().copied()
+    }
+
+    fn set_fscrypt_context(&mut self, context: FscryptContext) {
+        self.inode_impl
+            .block_manager
+            .fscrypt_context
+            .call_once(|| context);
+    }
+
+    /// Converts a name given by the user to the name of the entry on the disk.
+    ///
+    /// In an encrypted directory, the name is encrypted with the key. If the key is
+    /// absent, the name is a no-key name, which is resolved by finding the entry that it
+    /// represents, or this method fails with `ENOKEY` if `requires_key` is true.
+    fn disk_name<'a>(&self, name: &'a str, requires_key: bool) -> Result<Cow<'a, [u8]>> {
+        let Some(context) = self.fscrypt_context() else {
+            return Ok(Cow::Borrowed(name.as_bytes()));
+        };
+        if is_dot_or_dotdot(name) {
+            return Ok(Cow::Borrowed(name.as_bytes()));
+        }
+
+        match self.inode_impl.fs().fscrypt_keyring().file_key(&context) {
+            Ok(file_key) => Ok(Cow::Owned(file_key.encrypt_name(name.as_bytes()))),
+            Err(err) if requires_key => Err(err),
+            Err(_) => {
+                let nokey_name = NoKeyName::parse(name).ok_or(Error::new(Errno::ENOENT))?;
+                let mut dir_entry_reader = DirEntryReader::new(&self.page_cache, 0);
+                dir_entry_reader
+                    .iter_entries()
+                    .find(|(_, dir_entry)| nokey_name.matches(dir_entry.name()))
+                    .map(|(_, dir_entry)| Cow::Owned(dir_entry.name().to_vec()))
+                    .ok_or(Error::new(Errno::ENOENT))
+            }
+        }
+    }
+
+    pub fn contains_entry(&self, name: &[u8]) -> bool {
         DirEntryReader::new(&self.page_cache, 0).contains_entry(name)
     }
 
-    pub fn find_entry_item(&self, name: &str) -> Option<DirEntryItem> {
+    pub fn find_entry_item(&self, name: &[u8]) -> Option<DirEntryItem> {
         DirEntryReader::new(&self.page_cache, 0).find_entry_item(name)
     }
 
@@ -1120,11 +1533,12 @@ impl InodeInner {
         &mut self,
         ino: u32,
         inode_type: InodeType,
-        name: &str,
+        name: &[u8],
         check_existence: bool,
     ) -> Result<()> {
+        self.strip_dir_index()?;
         let entry_header = DirEntryHeader::new(ino, inode_type, name.len());
-        DirEntryWriter::new(&self.page_cache, 0).append_new_entry(
+        DirEntryWriter::new(&self.page_cache, 0, self.has_dir_tails()).append_new_entry(
             entry_header,
             name,
             check_existence,
@@ -1137,15 +1551,17 @@ impl InodeInner {
         }
 
         let is_dir = inode_type == InodeType::Dir;
-        let is_parent = name == "..";
+        let is_parent = name == b"..";
         if is_dir && !is_parent {
             self.inc_hard_links(); // for ".."
         }
         Ok(())
     }
 
-    pub fn remove_entry_at(&mut self, name: &str, offset: usize) -> Result<()> {
-        let removed_entry = DirEntryWriter::new(&self.page_cache, offset).remove_entry(name)?;
+    pub fn remove_entry_at(&mut self, name: &[u8], offset: usize) -> Result<()> {
+        self.strip_dir_index()?;
+        let removed_entry = DirEntryWriter::new(&self.page_cache, offset, self.has_dir_tails())
+            .remove_entry(name)?;
         let file_size = self.file_size();
         let page_cache_size = self.page_cache.pages().size();
         if page_cache_size < file_size {
@@ -1157,8 +1573,15 @@ impl InodeInner {
         Ok(())
     }
 
-    pub fn rename_entry_at(&mut self, old_name: &str, new_name: &str, offset: usize) -> Result<()> {
-        DirEntryWriter::new(&self.page_cache, offset).rename_entry(old_name, new_name)?;
+    pub fn rename_entry_at(
+        &mut self,
+        old_name: &[u8],
+        new_name: &[u8],
+        offset: usize,
+    ) -> Result<()> {
+        self.strip_dir_index()?;
+        DirEntryWriter::new(&self.page_cache, offset, self.has_dir_tails())
+            .rename_entry(old_name, new_name)?;
         let file_size = self.file_size();
         let page_cache_size = self.page_cache.pages().size();
         if page_cache_size != file_size {
@@ -1168,9 +1591,10 @@ impl InodeInner {
     }
 
     pub fn set_parent_ino(&mut self, parent_ino: u32) -> Result<()> {
-        let mut entry_item = self.find_entry_item("..").unwrap();
+        self.strip_dir_index()?;
+        let mut entry_item = self.find_entry_item(b"..").unwrap();
         entry_item.set_ino(parent_ino);
-        DirEntryWriter::new(&self.page_cache, entry_item.offset())
+        DirEntryWriter::new(&self.page_cache, entry_item.offset(), self.has_dir_tails())
             .write_header_only(entry_item.header())?;
         Ok(())
     }
@@ -1190,11 +1614,14 @@ impl InodeInner {
     pub fn file_perm(&self) -> FilePerm;
     pub fn set_file_perm(&mut self, perm: FilePerm);
     pub fn uid(&self) -> u32;
-    pub fn set_uid(&mut self, uid: u32);
+    pub fn set_uid(&mut self, uid: u32) -> Result<()>;
     pub fn gid(&self) -> u32;
-    pub fn set_gid(&mut self, gid: u32);
+    pub fn set_gid(&mut self, gid: u32) -> Result<()>;
     pub fn file_flags(&self) -> FileFlags;
+    pub fn insert_file_flags(&mut self, flags: FileFlags);
     pub fn hard_links(&self) -> u16;
+    pub fn charge_new_inode(&self) -> Result<()>;
+    pub fn release_new_inode(&self);
     pub fn nr_sectors_allocated(&self) -> usize;
     pub fn inc_hard_links(&mut self);
     pub fn dec_hard_links(&mut self);
@@ -1215,22 +1642,35 @@ impl InodeInner {
 struct InodeImpl {
     desc: Dirty<InodeDesc>,
     block_manager: Arc<InodeBlockManager>,
+    /// The seed of the checksums of the inode's metadata, if checksums are enabled.
+    csum_seed: Option<u32>,
     is_freed: bool,
     last_alloc_device_bid: Option<Ext2Bid>,
     weak_self: Weak<Inode>,
 }
 
 impl InodeImpl {
-    pub fn new(desc: Dirty<InodeDesc>, weak_self: Weak<Inode>, fs: Weak<Ext2>) -> Self {
+    pub fn new(
+        desc: Dirty<InodeDesc>,
+        extent_tree: Option<ExtentTree>,
+        csum_seed: Option<u32>,
+        weak_self: Weak<Inode>,
+        fs: Weak<Ext2>,
+    ) -> Self {
         let block_manager = InodeBlockManager {
             nblocks: AtomicUsize::new(desc.blocks_count() as _),
             block_ptrs: RwMutex::new(desc.block_ptrs),
             indirect_blocks: RwMutex::new(IndirectBlockCache::new(fs.clone())),
+            extent_tree: extent_tree.map(RwMutex::new),
+            is_dir: desc.type_ == InodeType::Dir,
+            dir_csum_seed: csum_seed.filter(|_| desc.type_ == InodeType::Dir),
+            fscrypt_context: Once::new(),
             fs,
         };
         Self {
             desc,
             block_manager: Arc::new(block_manager),
+            csum_seed,
             is_freed: false,
             last_alloc_device_bid: None,
             weak_self,
@@ -1265,22 +1705,42 @@ impl InodeImpl {
         self.desc.uid
     }
 
-    pub fn set_uid(&mut self, uid: u32) {
+    pub fn set_uid(&mut self, uid: u32) -> Result<()> {
+        let new_ids = QuotaIds {
+            uid,
+            ..self.quota_ids()
+        };
+        self.transfer_quotas(new_ids)?;
         self.desc.uid = uid;
+        Ok(())
     }
 
     pub fn gid(&self) -> u32 {
         self.desc.gid
     }
 
-    pub fn set_gid(&mut self, gid: u32) {
+    pub fn set_gid(&mut self, gid: u32) -> Result<()> {
+        let new_ids = QuotaIds {
+            gid,
+            ..self.quota_ids()
+        };
+        self.transfer_quotas(new_ids)?;
         self.desc.gid = gid;
+        Ok(())
     }
 
     pub fn file_flags(&self) -> FileFlags {
         self.desc.flags
     }
 
+    pub fn insert_file_flags(&mut self, flags: FileFlags) {
+        self.desc.flags.insert(flags);
+    }
+
+    pub fn remove_file_flags(&mut self, flags: FileFlags) {
+        self.desc.flags.remove(flags);
+    }
+
     pub fn hard_links(&self) -> u16 {
         self.desc.hard_links
     }
@@ -1364,7 +1824,12 @@ impl InodeImpl {
     }
 
     pub fn sync_metadata(&mut self) -> Result<()> {
-        if !self.desc.is_dirty() {
+        let is_extent_tree_dirty = self
+            .block_manager
+            .extent_tree
+            .as_ref()
+            .is_some_and(|extent_tree| extent_tree.read().is_dirty());
+        if !self.desc.is_dirty() && !is_extent_tree_dirty {
             return Ok(());
         }
 
@@ -1376,6 +1841,9 @@ impl InodeImpl {
                 inode
                     .fs()
                     .free_inode(inode.ino(), self.desc.type_ == InodeType::Dir)?;
+                if let Some((fs, ids)) = self.charged_quotas() {
+                    fs.disk_quotas().release(&ids, 0, 1);
+                }
                 if let Some(xattr) = &inode.xattr {
                     xattr.free()?;
                 }
@@ -1384,6 +1852,16 @@ impl InodeImpl {
         }
 
         self.block_manager.indirect_blocks.write().evict_all()?;
+        if let Some(extent_tree) = self.block_manager.extent_tree.as_ref() {
+            let mut extent_tree = extent_tree.write();
+            extent_tree.store(
+                &mut self.desc.block_ptrs,
+                &inode.fs(),
+                inode.block_group_idx,
+                self.csum_seed,
+            )?;
+            self.desc.set_data_blocks(extent_tree.nr_blocks());
+        }
         inode.fs().sync_inode(inode.ino(), &self.desc)?;
         self.desc.clear_dirty();
         Ok(())
@@ -1402,6 +1880,50 @@ impl InodeImpl {
         Ok(())
     }
 
+    /// Returns the file system and the IDs if the inode is charged to the disk quotas.
+    ///
+    /// The quota files themselves are not charged.
+    fn charged_quotas(&self) -> Option<(Arc<Ext2>, QuotaIds)> {
+        let fs = self.fs();
+        if fs.disk_quotas().is_quota_file(self.inode().ino() as u64) {
+            return None;
+        }
+        let ids = self.quota_ids();
+        Some((fs, ids))
+    }
+
+    /// Charges the newly created inode to the disk quotas.
+    pub fn charge_new_inode(&self) -> Result<()> {
+        let Some((fs, ids)) = self.charged_quotas() else {
+            return Ok(());
+        };
+        fs.disk_quotas().charge(&ids, 0, 1)
+    }
+
+    /// Releases the newly created inode from the disk quotas if its creation fails.
+    pub fn release_new_inode(&self) {
+        if let Some((fs, ids)) = self.charged_quotas() {
+            fs.disk_quotas().release(&ids, 0, 1);
+        }
+    }
+
+    /// Transfers the usage of the inode to the new IDs in the disk quotas.
+    fn transfer_quotas(&self, new_ids: QuotaIds) -> Result<()> {
+        let Some((fs, ids)) = self.charged_quotas() else {
+            return Ok(());
+        };
+        let space = self.desc.blocks_count() as u64 * BLOCK_SIZE as u64;
+        fs.disk_quotas().transfer(&ids, &new_ids, space, 1)
+    }
+
+    fn quota_ids(&self) -> QuotaIds {
+        QuotaIds {
+            uid: self.desc.uid,
+            gid: self.desc.gid,
+            projid: 0,
+        }
+    }
+
     /// Expands inode size.
     ///
     /// After a successful expansion, the size will be enlarged to `new_size`,
@@ -1415,7 +1937,18 @@ impl InodeImpl {
             if new_blocks - old_blocks > self.fs().super_block().free_blocks_count() {
                 return_errno_with_message!(Errno::ENOSPC, "not enough free blocks");
             }
-            self.expand_blocks(old_blocks..new_blocks)?;
+
+            let quota_space = (new_blocks - old_blocks) as u64 * BLOCK_SIZE as u64;
+            let quotas = self.charged_quotas();
+            if let Some((fs, ids)) = quotas.as_ref() {
+                fs.disk_quotas().charge(ids, quota_space, 0)?;
+            }
+            if let Err(err) = self.expand_blocks(old_blocks..new_blocks) {
+                if let Some((fs, ids)) = quotas.as_ref() {
+                    fs.disk_quotas().release(ids, quota_space, 0);
+                }
+                return Err(err);
+            }
         }
 
         // Expands the size
@@ -1427,6 +1960,10 @@ impl InodeImpl {
     ///
     /// After a successful expansion, the block count will be enlarged to `range.end`.
     fn expand_blocks(&mut self, range: Range<Ext2Bid>) -> Result<()> {
+        if self.block_manager.extent_tree.is_some() {
+            return self.expand_extent_blocks(range);
+        }
+
         let mut current_range = range.clone();
         while !current_range.is_empty() {
             let Ok(expand_cnt) = self.try_expand_blocks(current_range.clone()) else {
@@ -1439,6 +1976,43 @@ impl InodeImpl {
         Ok(())
     }
 
+    /// Expands inode blocks that are mapped by the extent tree.
+    ///
+    /// The blocks that have been mapped beyond the end of the file are kept.
+    fn expand_extent_blocks(&mut self, range: Range<Ext2Bid>) -> Result<()> {
+        let fs = self.fs();
+        let block_group_idx = self.inode().block_group_idx;
+        let mut extent_tree = self.block_manager.extent_tree.as_ref().unwrap().write();
+
+        let mut current = range.start;
+        while current < range.end {
+            let hole_len = match extent_tree.lookup(current, range.end - current) {
+                ExtentMapping::Hole(len) => len,
+                ExtentMapping::Mapped(device_range) | ExtentMapping::Unwritten(device_range) => {
+                    current += device_range.len() as Ext2Bid;
+                    continue;
+                }
+            };
+
+            let goal_group_idx = extent_tree.goal(current).map_or(block_group_idx, |bid| {
+                (bid / fs.blocks_per_group()) as usize
+            });
+            let Some(device_range) = fs.alloc_blocks(goal_group_idx, hole_len) else {
+                for device_range in extent_tree.truncate(range.start) {
+                    fs.free_blocks(device_range).unwrap();
+                }
+                self.desc.set_data_blocks(extent_tree.nr_blocks());
+                return_errno_with_message!(Errno::ENOSPC, "can not allocate blocks");
+            };
+            let nr_blocks = device_range.len() as Ext2Bid;
+            extent_tree.insert(current, device_range);
+            current += nr_blocks;
+        }
+
+        self.desc.set_data_blocks(extent_tree.nr_blocks());
+        Ok(())
+    }
+
     /// Attempts to expand a range of blocks and returns the number of consecutive
     /// blocks successfully allocated.
     ///
@@ -1670,9 +2244,17 @@ impl InodeImpl {
         let new_blocks = self.desc.size_to_blocks(new_size);
         let old_blocks = self.desc.blocks_count();
 
-        // Shrinks block count if necessary
-        if new_blocks < old_blocks {
-            self.shrink_blocks(new_blocks..old_blocks);
+        // Shrinks block count if necessary. The extent tree may also map the blocks
+        // beyond the end of the file, which are freed as well.
+        if new_blocks < old_blocks || self.block_manager.extent_tree.is_some() {
+            self.shrink_blocks(new_blocks..old_blocks.max(new_blocks));
+        }
+
+        if new_blocks < old_blocks
+            && let Some((fs, ids)) = self.charged_quotas()
+        {
+            let quota_space = (old_blocks - new_blocks) as u64 * BLOCK_SIZE as u64;
+            fs.disk_quotas().release(&ids, quota_space, 0);
         }
 
         // Shrinks the size
@@ -1690,6 +2272,16 @@ impl InodeImpl {
     ///
     /// After the reduction, the block count will be decreased to `range.start`.
     fn shrink_blocks(&mut self, range: Range<Ext2Bid>) {
+        if let Some(extent_tree) = self.block_manager.extent_tree.as_ref() {
+            let fs = self.fs();
+            let mut extent_tree = extent_tree.write();
+            for device_range in extent_tree.truncate(range.start) {
+                fs.free_blocks(device_range).unwrap();
+            }
+            self.desc.set_data_blocks(extent_tree.nr_blocks());
+            return;
+        }
+
         let mut current_range = range.clone();
         while !current_range.is_empty() {
             let free_cnt = self.try_shrink_blocks(current_range.clone());
@@ -1854,6 +2446,18 @@ struct InodeBlockManager {
     /// frequent reads access the `InodeDesc` copy without locking.
     block_ptrs: RwMutex<BlockPtrs>,
     indirect_blocks: RwMutex<IndirectBlockCache>,
+    /// The extent tree that maps the blocks if the inode uses extents,
+    /// in which case the block pointers and the indirect blocks are unused.
+    extent_tree: Option<RwMutex<ExtentTree>>,
+    /// Whether the inode is a directory, whose blocks are written as metadata.
+    is_dir: bool,
+    /// The seed of the checksums in the directory blocks,
+    /// if the inode is a directory and the checksums are enabled.
+    dir_csum_seed: Option<u32>,
+    /// The encryption context, if the inode is encrypted.
+    ///
+    /// The blocks of an encrypted regular file are encrypted on the disk.
+    fscrypt_context: Once<FscryptContext>,
     fs: Weak<Ext2>,
 }
 
@@ -1876,6 +2480,10 @@ impl InodeBlockManager {
 
     pub fn read_blocks(&self, bid: Ext2Bid, nblocks: usize, writer: &mut VmWriter) -> Result<()> {
         debug_assert!(nblocks * BLOCK_SIZE <= writer.avail());
+        if self.extent_tree.is_some() {
+            return self.read_extent_blocks(bid, nblocks, writer);
+        }
+
         let bio_waiter = self.read_blocks_async(bid, nblocks)?;
         if Some(BioStatus::Complete) != bio_waiter.wait() {
             return_errno!(Errno::EIO);
@@ -1893,11 +2501,79 @@ impl InodeBlockManager {
         Ok(())
     }
 
+    /// Reads the blocks mapped by the extent tree synchronously.
+    ///
+    /// The holes and the unwritten blocks are read as zeros.
+    fn read_extent_blocks(
+        &self,
+        bid: Ext2Bid,
+        nblocks: usize,
+        writer: &mut VmWriter,
+    ) -> Result<()> {
+        let extent_tree = self.extent_tree.as_ref().unwrap().read();
+        let end = bid + nblocks as Ext2Bid;
+        let mut current = bid;
+        while current < end {
+            let zeroed_nblocks = match extent_tree.lookup(current, end - current) {
+                ExtentMapping::Mapped(device_range) => {
+                    let range_nblocks = device_range.len();
+                    let bio_segment = BioSegment::alloc(range_nblocks, BioDirection::FromDevice);
+                    self.fs()
+                        .read_blocks(device_range.start, bio_segment.clone())?;
+                    bio_segment
+                        .reader()?
+                        .read_fallible(writer)
+                        .map_err(|(e, _)| Error::from(e))?;
+                    current += range_nblocks as Ext2Bid;
+                    continue;
+                }
+                ExtentMapping::Unwritten(device_range) => device_range.len() as Ext2Bid,
+                ExtentMapping::Hole(len) => len,
+            };
+            writer
+                .fill_zeros(zeroed_nblocks as usize * BLOCK_SIZE)
+                .map_err(|(e, _)| Error::from(e))?;
+            current += zeroed_nblocks;
+        }
+
+        Ok(())
+    }
+
     pub fn read_block_async(&self, bid: Ext2Bid, frame: &CachePage) -> Result<BioWaiter> {
+        if let Some(file_key) = self.file_key()? {
+            return self.read_encrypted_block(bid, frame, &file_key);
+        }
+
         let mut bio_waiter = BioWaiter::new();
 
+        if let Some(extent_tree) = self.extent_tree.as_ref() {
+            let ExtentMapping::Mapped(device_range) = extent_tree.read().lookup(bid, 1) else {
+                // The holes and the unwritten blocks are read as zeros.
+                frame.writer().fill_zeros(BLOCK_SIZE);
+                return Ok(bio_waiter);
+            };
+            if self.is_dir {
+                return self.fs().read_metadata_blocks_async(
+                    device_range.start,
+                    Segment::from(frame.clone()).into(),
+                );
+            }
+            let bio_segment = BioSegment::new_from_segment(
+                Segment::from(frame.clone()).into(),
+                BioDirection::FromDevice,
+            );
+            return self.fs().read_blocks_async(device_range.start, bio_segment);
+        }
+
         for dev_range in DeviceRangeReader::new(self, bid..bid + 1 as Ext2Bid)? {
             let start_bid = dev_range.start as Ext2Bid;
+            if self.is_dir {
+                let waiter = self
+                    .fs()
+                    .read_metadata_blocks_async(start_bid, Segment::from(frame.clone()).into())?;
+                bio_waiter.concat(waiter);
+                continue;
+            }
             // TODO: Should we allocate the bio segment from the pool on reads?
             // This may require an additional copy to the requested frame in the completion callback.
             let bio_segment = BioSegment::new_from_segment(
@@ -1911,6 +2587,53 @@ impl InodeBlockManager {
         Ok(bio_waiter)
     }
 
+    /// Reads and decrypts a block of an encrypted file synchronously.
+    ///
+    /// The holes are read as zeros without decryption.
+    fn read_encrypted_block(
+        &self,
+        bid: Ext2Bid,
+        frame: &CachePage,
+        file_key: &FscryptFileKey,
+    ) -> Result<BioWaiter> {
+        let device_bid = match self.extent_tree.as_ref() {
+            Some(extent_tree) => match extent_tree.read().lookup(bid, 1) {
+                ExtentMapping::Mapped(device_range) => Some(device_range.start),
+                _ => None,
+            },
+            None => DeviceRangeReader::new(self, bid..bid + 1)?
+                .next()
+                .map(|device_range| device_range.start as Ext2Bid),
+        };
+        let Some(device_bid) = device_bid else {
+            frame.writer().fill_zeros(BLOCK_SIZE);
+            return Ok(BioWaiter::new());
+        };
+
+        let bio_segment = BioSegment::alloc(1, BioDirection::FromDevice);
+        self.fs().read_blocks(device_bid, bio_segment.clone())?;
+        let mut buf = vec![0u8; BLOCK_SIZE];
+        bio_segment
+            .reader()
+            .unwrap()
+            .read(&mut VmWriter::from(buf.as_mut_slice()));
+        file_key.decrypt_block(bid as u64, &mut buf);
+        frame.writer().write(&mut VmReader::from(buf.as_slice()));
+
+        Ok(BioWaiter::new())
+    }
+
+    /// Returns the key of the contents if the inode is an encrypted regular file.
+    fn file_key(&self) -> Result<Option<FscryptFileKey>> {
+        if self.is_dir {
+            return Ok(None);
+        }
+        let Some(context) = self.fscrypt_context.get() else {
+            return Ok(None);
+        };
+        self.fs().fscrypt_keyring().file_key(context).map(Some)
+    }
+
     /// Writes one or multiple blocks from the segment start from `bid` asynchronously.
     pub fn write_blocks_async(
         &self,
@@ -1921,7 +2644,7 @@ impl InodeBlockManager {
         debug_assert_eq!(nblocks * BLOCK_SIZE, reader.remain());
         let mut bio_waiter = BioWaiter::new();
 
-        for dev_range in DeviceRangeReader::new(self, bid..bid + nblocks as Ext2Bid)? {
+        for dev_range in self.device_ranges_for_write(bid..bid + nblocks as Ext2Bid)? {
             let start_bid = dev_range.start as Ext2Bid;
             let range_nblocks = dev_range.len();
 
@@ -1943,16 +2666,41 @@ impl InodeBlockManager {
     }
 
     pub fn write_block_async(&self, bid: Ext2Bid, frame: &CachePage) -> Result<BioWaiter> {
+        let file_key = self.file_key()?;
         let mut bio_waiter = BioWaiter::new();
 
-        for dev_range in DeviceRangeReader::new(self, bid..bid + 1 as Ext2Bid)? {
+        for dev_range in self.device_ranges_for_write(bid..bid + 1 as Ext2Bid)? {
             let start_bid = dev_range.start as Ext2Bid;
+            if self.is_dir {
+                let mut buf = vec![0u8; BLOCK_SIZE];
+                frame.reader().read(&mut VmWriter::from(buf.as_mut_slice()));
+                if let Some(seed) = self.dir_csum_seed {
+                    set_tail_checksum(&mut buf, seed);
+                }
+                let waiter = self
+                    .fs()
+                    .write_metadata_bytes_async(start_bid as usize * BLOCK_SIZE, &buf)?;
+                bio_waiter.concat(waiter);
+                continue;
+            }
             let bio_segment = BioSegment::alloc(1, BioDirection::ToDevice);
-            // This requires an additional copy to the pooled bio segment.
-            bio_segment
-                .writer()
-                .unwrap()
-                .write_fallible(&mut frame.reader().to_fallible())?;
+            if let Some(file_key) = file_key.as_ref() {
+                // The page cache holds the plaintext, so the ciphertext is written
+                // from another buffer.
+                let mut buf = vec![0u8; BLOCK_SIZE];
+                frame.reader().read(&mut VmWriter::from(buf.as_mut_slice()));
+                file_key.encrypt_block(bid as u64, &mut buf);
+                bio_segment
+                    .writer()
+                    .unwrap()
+                    .write(&mut VmReader::from(buf.as_slice()));
+            } else {
+                // This requires an additional copy to the pooled bio segment.
+                bio_segment
+                    .writer()
+                    .unwrap()
+                    .write_fallible(&mut frame.reader().to_fallible())?;
+            }
             let waiter = self.fs().write_blocks_async(start_bid, bio_segment)?;
             bio_waiter.concat(waiter);
         }
@@ -1960,6 +2708,44 @@ impl InodeBlockManager {
         Ok(bio_waiter)
     }
 
+    /// Returns the device ranges that the blocks in `range` are written to.
+    ///
+    /// If the inode uses extents, the holes are allocated and the unwritten blocks
+    /// are marked as written.
+    fn device_ranges_for_write(&self, range: Range<Ext2Bid>) -> Result<Vec<Range<Ext2Bid>>> {
+        let Some(extent_tree) = self.extent_tree.as_ref() else {
+            return Ok(DeviceRangeReader::new(self, range)?.collect());
+        };
+
+        let fs = self.fs();
+        let mut extent_tree = extent_tree.write();
+        let mut device_ranges = Vec::new();
+        let mut current = range.start;
+        while current < range.end {
+            let device_range = match extent_tree.lookup(current, range.end - current) {
+                ExtentMapping::Mapped(device_range) => device_range,
+                ExtentMapping::Unwritten(device_range) => {
+                    extent_tree.mark_written(current..current + device_range.len() as Ext2Bid);
+                    device_range
+                }
+                ExtentMapping::Hole(len) => {
+                    let goal_group_idx = extent_tree
+                        .goal(current)
+                        .map_or(0, |bid| (bid / fs.blocks_per_group()) as usize);
+                    let device_range = fs
+                        .alloc_blocks(goal_group_idx, len)
+                        .ok_or_else(|| Error::with_message(Errno::ENOSPC, "no space on device"))?;
+                    extent_tree.insert(current, device_range.clone());
+                    device_range
+                }
+            };
+            current += device_range.len() as Ext2Bid;
+            device_ranges.push(device_range);
+        }
+
+        Ok(device_ranges)
+    }
+
     pub fn nblocks(&self) -> usize {
         self.nblocks.load(Ordering::Acquire)
     }
@@ -2142,13 +2928,15 @@ pub(super) struct InodeDesc {
     /// Hard links count.
     hard_links: u16,
     /// Number of sectors.
-    sector_count: u32,
+    sector_count: u64,
     /// File flags.
     flags: FileFlags,
     /// Pointers to blocks.
     block_ptrs: BlockPtrs,
     /// File or directory acl block.
     acl: Bid,
+    /// File version, which is also part of the seed of the inode's checksums.
+    generation: u32,
 }
 
 impl TryFrom<RawInode> for InodeDesc {
@@ -2156,6 +2944,8 @@ impl TryFrom<RawInode> for InodeDesc {
 
     fn try_from(inode: RawInode) -> Result<Self> {
         let inode_type = InodeType::from_raw_mode(inode.mode)?;
+        let flags = FileFlags::from_bits(inode.flags)
+            .ok_or(Error::with_message(Errno::EINVAL, "invalid file flags"))?;
         Ok(Self {
             type_: inode_type,
             perm: FilePerm::from_raw_mode(inode.mode)?,
@@ -2171,15 +2961,25 @@ impl TryFrom<RawInode> for InodeDesc {
             mtime: Duration::from(inode.mtime),
             dtime: Duration::from(inode.dtime),
             hard_links: inode.hard_links,
-            sector_count: inode.sector_count,
-            flags: FileFlags::from_bits(inode.flags)
-                .ok_or(Error::with_message(Errno::EINVAL, "invalid file flags"))?,
+            sector_count: {
+                let count =
+                    ((inode.os_dependent_2.blocks_high as u64) << 32) | inode.sector_count as u64;
+                // The count is in the unit of blocks for huge files.
+                if flags.contains(FileFlags::HUGE_FILE) {
+                    count * (BLOCK_SIZE / SECTOR_SIZE) as u64
+                } else {
+                    count
+                }
+            },
+            flags: flags - FileFlags::HUGE_FILE,
             block_ptrs: inode.block_ptrs,
             acl: match inode_type {
-                InodeType::File => Bid::new(inode.file_acl as _),
-                InodeType::Dir => Bid::new(inode.size_high as _),
+                InodeType::File | InodeType::Dir => Bid::new(
+                    ((inode.os_dependent_2.file_acl_high as u64) << 32) | inode.file_acl as u64,
+                ),
                 _ => Bid::new(0),
             },
+            generation: inode.generation,
         })
     }
 }
@@ -2188,6 +2988,8 @@ impl InodeDesc {
     pub fn new(type_: InodeType, perm: FilePerm) -> Dirty<Self> {
         let now = now();
         let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+        let mut generation = 0u32;
+        getrandom(generation.as_mut_bytes());
         Dirty::new_dirty(Self {
             type_,
             perm,
@@ -2203,9 +3005,27 @@ impl InodeDesc {
             flags: FileFlags::empty(),
             block_ptrs: BlockPtrs::default(),
             acl: Bid::new(0),
+            generation,
         })
     }
 
+    /// Returns the file version.
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// Returns the file flags.
+    pub fn file_flags(&self) -> FileFlags {
+        self.flags
+    }
+
+    /// Makes the new inode map its blocks with an empty extent tree.
+    pub fn init_extent_root(&mut self) {
+        debug_assert_eq!(self.sector_count, 0);
+        self.flags.insert(FileFlags::EXTENTS);
+        ExtentTree::init_root(&mut self.block_ptrs);
+    }
+
     pub fn num_page_bytes(&self) -> usize {
         (self.blocks_count() as usize) * BLOCK_SIZE
     }
@@ -2229,7 +3049,7 @@ impl InodeDesc {
     }
 
     /// Returns the number of sectors used for ACL.
-    fn acl_sectors(&self) -> u32 {
+    fn acl_sectors(&self) -> u64 {
         if self.acl.to_raw() == 0 {
             return 0;
         }
@@ -2246,7 +3066,7 @@ impl InodeDesc {
     }
 
     /// Returns the number of data sectors, including the indirect blocks.
-    fn data_sectors(&self) -> u32 {
+    fn data_sectors(&self) -> u64 {
         self.sector_count - self.acl_sectors()
     }
 
@@ -2263,15 +3083,13 @@ impl InodeDesc {
     }
 }
 
-fn sectors_to_blocks(sector_count: u32) -> Ext2Bid {
-    sector_count.div_ceil((BLOCK_SIZE / SECTOR_SIZE) as u32)
+fn sectors_to_blocks(sector_count: u64) -> Ext2Bid {
+    sector_count.div_ceil((BLOCK_SIZE / SECTOR_SIZE) as u64) as Ext2Bid
 }
 
-fn blocks_to_sectors(block_count: u32) -> u32 {
-    const SECTORS_PER_BLOCK: u32 = (BLOCK_SIZE / SECTOR_SIZE) as u32;
-    block_count
-        .checked_mul(SECTORS_PER_BLOCK)
-        .expect("sector count overflow in blocks_to_sectors conversion")
+fn blocks_to_sectors(block_count: u32) -> u64 {
+    const SECTORS_PER_BLOCK: u64 = (BLOCK_SIZE / SECTOR_SIZE) as u64;
+    block_count as u64 * SECTORS_PER_BLOCK
 }
 
 bitflags! {
@@ -2349,6 +3167,22 @@ bitflags! {
         const DIR_SYNC = 1 << 16;
         /// Top of directory hierarchies.
         const TOP_DIR = 1 << 17;
+        /// The sector count is in the unit of blocks.
+        const HUGE_FILE = 1 << 18;
+        /// The blocks are mapped by an extent tree.
+        const EXTENTS = 1 << 19;
+        /// Verity protected file.
+        const VERITY = 1 << 20;
+        /// Inode used for a large extended attribute.
+        const EA_INODE = 1 << 21;
+        /// Direct access to the storage.
+        const DAX = 1 << 25;
+        /// The data is stored inline in the inode.
+        const INLINE_DATA = 1 << 28;
+        /// Create with the parent's project ID.
+        const PROJ_INHERIT = 1 << 29;
+        /// Case-insensitive directory.
+        const CASEFOLD = 1 << 30;
         /// Reserved for ext2 lib.
         const RESERVED = 1 << 31;
     }
@@ -2411,20 +3245,19 @@ impl From<&InodeDesc> for RawInode {
             dtime: UnixTime::from(inode.dtime),
             gid: inode.gid as u16,
             hard_links: inode.hard_links,
-            sector_count: inode.sector_count,
+            sector_count: inode.sector_count as u32,
             flags: inode.flags.bits(),
             block_ptrs: inode.block_ptrs,
-            file_acl: if inode.type_ == InodeType::File {
-                inode.acl.to_raw() as u32
-            } else {
-                0
-            },
-            size_high: if inode.type_ == InodeType::Dir {
-                inode.acl.to_raw() as u32
+            generation: inode.generation,
+            file_acl: inode.acl.to_raw() as u32,
+            size_high: if inode.type_ == InodeType::File {
+                (inode.size >> 32) as u32
             } else {
                 0
             },
             os_dependent_2: Osd2 {
+                blocks_high: (inode.sector_count >> 32) as u16,
+                file_acl_high: (inode.acl.to_raw() >> 32) as u16,
                 uid_high: (inode.uid >> 16) as u16,
                 gid_high: (inode.gid >> 16) as u16,
                 ..Default::default()
@@ -2434,20 +3267,57 @@ impl From<&InodeDesc> for RawInode {
     }
 }
 
+impl RawInode {
+    /// The offset of the low 16 bits of the checksum.
+    const CHECKSUM_LO_OFFSET: usize = 0x7c;
+    /// The offset of the high 16 bits of the checksum in the extra fields.
+    const CHECKSUM_HI_OFFSET: usize = 0x82;
+    /// The offset of the size of the extra fields.
+    const EXTRA_ISIZE_OFFSET: usize = 0x80;
+
+    /// Updates the checksum of the on-disk inode `buf`, including its extra fields.
+    ///
+    /// The high 16 bits of the checksum are stored only if the extra fields are large enough.
+    pub(super) fn update_checksum(buf: &mut [u8], fs_seed: u32, ino: u32) {
+        let has_checksum_hi = buf.len() > size_of::<RawInode>()
+            && u16::from_le_bytes([
+                buf[Self::EXTRA_ISIZE_OFFSET],
+                buf[Self::EXTRA_ISIZE_OFFSET + 1],
+            ]) >= 4;
+        let generation = RawInode::from_first_bytes(buf).generation;
+
+        buf[Self::CHECKSUM_LO_OFFSET..Self::CHECKSUM_LO_OFFSET + 2].fill(0);
+        if has_checksum_hi {
+            buf[Self::CHECKSUM_HI_OFFSET..Self::CHECKSUM_HI_OFFSET + 2].fill(0);
+        }
+        let seed = crc32c(fs_seed, &ino.to_le_bytes());
+        let seed = crc32c(seed, &generation.to_le_bytes());
+        let checksum = crc32c(seed, buf);
+
+        buf[Self::CHECKSUM_LO_OFFSET..Self::CHECKSUM_LO_OFFSET + 2]
+            .copy_from_slice(&(checksum as u16).to_le_bytes());
+        if has_checksum_hi {
+            buf[Self::CHECKSUM_HI_OFFSET..Self::CHECKSUM_HI_OFFSET + 2]
+                .copy_from_slice(&((checksum >> 16) as u16).to_le_bytes());
+        }
+    }
+}
+
 /// OS dependent Value 2
 #[repr(C)]
 #[derive(Clone, Copy, Default, Debug, Pod)]
 pub(super) struct Osd2 {
-    /// Fragment number.
-    pub frag_num: u8,
-    /// Fragment size.
-    pub frag_size: u8,
-    pad1: u16,
+    /// High 16 bits of the sector count.
+    pub blocks_high: u16,
+    /// High 16 bits of the File ACL.
+    pub file_acl_high: u16,
     /// High 16 bits of User Id.
     pub uid_high: u16,
     /// High 16 bits of Group Id.
     pub gid_high: u16,
-    reserved2: u32,
+    /// Low 16 bits of the checksum.
+    pub checksum_lo: u16,
+    reserved: u16,
 }
 
 fn is_block_aligned(offset: usize) -> bool {
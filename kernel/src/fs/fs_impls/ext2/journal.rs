@@ -0,0 +1,1399 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The journal of Ext3 and Ext4, whose on-disk format is compatible with JBD2 of Linux.
+//!
+//! The modified metadata blocks are collected in the running transaction instead of
+//! being written to their home locations. When the transaction commits, the blocks are
+//! written to the journal, followed by a commit block. Only after that, the blocks are
+//! checkpointed, i.e., written to their home locations, and the journal becomes empty
+//! again. If the system crashes before the checkpoint completes, the committed
+//! transaction is replayed when the filesystem is mounted next time.
+//!
+//! The journal works in the ordered mode: the file data are written to the device
+//! before the metadata referring to them are committed.
+//!
+//! Reference: <https://docs.kernel.org/filesystems/ext4/journal.html>
+
+use core::fmt::Debug;
+
+use super::{
+    block_group::RawGroupDescriptor,
+    block_ptr::{BID_SIZE, BlockPtrs, DIRECT_RANGE, Ext2Bid},
+    checksum::crc32c,
+    extent::{ExtentMapping, ExtentTree},
+    inode::{FileFlags, RawInode},
+    prelude::*,
+    super_block::SuperBlock,
+    utils::now,
+};
+
+/// The magic number of the journal blocks.
+const JOURNAL_MAGIC: u32 = 0xc03b_3998;
+
+/// The size of the journal superblock covered by its checksum.
+const JOURNAL_SUPER_BLOCK_SIZE: usize = 1024;
+
+/// The checksum type that represents CRC32C.
+const CHECKSUM_TYPE_CRC32C: u8 = 4;
+
+/// The size of the UUID following the first tag in a descriptor block.
+const UUID_SIZE: usize = 16;
+
+/// The offset of the checksum in a commit block.
+const COMMIT_CHECKSUM_OFFSET: usize = 16;
+
+/// The offset of the commit time in a commit block.
+const COMMIT_TIME_OFFSET: usize = 48;
+
+/// The offset of the number of used bytes in a revoke block.
+const REVOKE_COUNT_OFFSET: usize = 12;
+
+/// The size of the header of a revoke block.
+const REVOKE_HEADER_SIZE: usize = 16;
+
+/// The journal.
+pub(super) struct Journal {
+    /// The device ranges of the journal blocks, in the order of the journal.
+    device_ranges: Vec<Range<Ext2Bid>>,
+    /// The index of the first block of the log.
+    first: u32,
+    /// The number of blocks in the journal.
+    max_len: u32,
+    /// The incompatible features.
+    feature_incompat: JournalFeatureInCompatSet,
+    /// The seed of the checksums, if the checksums are enabled.
+    csum_seed: Option<u32>,
+    /// The UUID of the journal.
+    uuid: [u8; 16],
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    /// The sequence number of the running transaction.
+    sequence: u32,
+    /// The metadata blocks modified in the running transaction.
+    blocks: BTreeMap<Ext2Bid, Box<[u8]>>,
+    /// The block of the journal superblock.
+    super_block: Box<[u8]>,
+}
+
+impl Journal {
+    /// Loads the journal of the filesystem described by `super_block`.
+    ///
+    /// If the filesystem needs recovery, the committed transactions are replayed.
+    /// The journal is empty after loading.
+    pub fn load(block_device: &dyn BlockDevice, super_block: &SuperBlock) -> Result<Self> {
+        let raw_inode = read_journal_inode(block_device, super_block)?;
+        let nblocks = {
+            let size = ((raw_inode.size_high as u64) << 32) | raw_inode.size_low as u64;
+            (size / BLOCK_SIZE as u64).min(Ext2Bid::MAX as u64) as Ext2Bid
+        };
+        let device_ranges = map_journal_blocks(block_device, &raw_inode, nblocks)?;
+
+        let mut super_block_buf = vec![0u8; BLOCK_SIZE].into_boxed_slice();
+        let first_bid = device_ranges.first().map_or(0, |range| range.start);
+        block_device.read_bytes(first_bid as usize * BLOCK_SIZE, &mut super_block_buf)?;
+        let raw_super_block = RawJournalSuperBlock::from_first_bytes(&super_block_buf);
+
+        let header = raw_super_block.header.parse();
+        if header.magic != JOURNAL_MAGIC {
+            return_errno_with_message!(Errno::EINVAL, "bad journal magic number");
+        }
+        let feature_incompat = match header.block_type() {
+            Some(JournalBlockType::SuperBlockV1) => JournalFeatureInCompatSet::empty(),
+            Some(JournalBlockType::SuperBlockV2) => {
+                if u32::from_be(raw_super_block.feature_compat) != 0
+                    || u32::from_be(raw_super_block.feature_ro_compat) != 0
+                {
+                    return_errno_with_message!(Errno::EINVAL, "unsupported journal features");
+                }
+                let feature_incompat = JournalFeatureInCompatSet::from_bits(u32::from_be(
+                    raw_super_block.feature_incompat,
+                ))
+                .ok_or(Error::with_message(
+                    Errno::EINVAL,
+                    "invalid journal feature incompat set",
+                ))?;
+                if feature_incompat.intersects(JournalFeatureInCompatSet::UNSUPPORTED)
+                    || feature_incompat.contains(
+                        JournalFeatureInCompatSet::CSUM_V2 | JournalFeatureInCompatSet::CSUM_V3,
+                    )
+                {
+                    return_errno_with_message!(Errno::EINVAL, "unsupported journal features");
+                }
+                feature_incompat
+            }
+            _ => return_errno_with_message!(Errno::EINVAL, "bad journal superblock"),
+        };
+
+        if u32::from_be(raw_super_block.block_size) as usize != BLOCK_SIZE {
+            return_errno_with_message!(Errno::EINVAL, "unsupported journal block size");
+        }
+        let max_len = u32::from_be(raw_super_block.max_len);
+        let first = u32::from_be(raw_super_block.first);
+        if max_len > nblocks || first == 0 || first >= max_len {
+            return_errno_with_message!(Errno::EINVAL, "bad journal size");
+        }
+
+        let csum_seed = if feature_incompat
+            .intersects(JournalFeatureInCompatSet::CSUM_V2 | JournalFeatureInCompatSet::CSUM_V3)
+        {
+            if raw_super_block.checksum_type != CHECKSUM_TYPE_CRC32C {
+                return_errno_with_message!(Errno::EINVAL, "invalid journal checksum type");
+            }
+            if raw_super_block.checksum != super_block_checksum(&super_block_buf).to_be() {
+                return_errno_with_message!(Errno::EBADMSG, "bad journal superblock checksum");
+            }
+            Some(crc32c(!0, &raw_super_block.uuid))
+        } else {
+            None
+        };
+
+        let journal = Self {
+            device_ranges,
+            first,
+            max_len,
+            feature_incompat,
+            csum_seed,
+            uuid: raw_super_block.uuid,
+            inner: Mutex::new(Inner {
+                sequence: 0,
+                blocks: BTreeMap::new(),
+                super_block: super_block_buf,
+            }),
+        };
+
+        if journal.max_transaction_blocks() == 0 {
+            return_errno_with_message!(Errno::EINVAL, "the journal is too small");
+        }
+
+        let start = u32::from_be(raw_super_block.start);
+        let mut sequence = u32::from_be(raw_super_block.sequence);
+        if start != 0 {
+            if super_block.needs_recovery() {
+                sequence = journal.recover(block_device, start, sequence)?;
+            } else {
+                // Like Linux, the journal is discarded if the filesystem does not need
+                // recovery.
+                warn!("the journal is not empty, but the filesystem needs no recovery");
+            }
+        }
+
+        // Empties the journal.
+        let mut inner = journal.inner.lock();
+        inner.sequence = sequence;
+        journal.write_super_block(block_device, &mut inner, 0)?;
+        flush(block_device)?;
+        drop(inner);
+
+        Ok(journal)
+    }
+
+    /// Writes a metadata block to the running transaction.
+    pub fn write_block(&self, bid: Ext2Bid, buf: &[u8]) {
+        debug_assert_eq!(buf.len(), BLOCK_SIZE);
+        self.inner.lock().blocks.insert(bid, buf.into());
+    }
+
+    /// Reads a metadata block from the running transaction.
+    ///
+    /// Returns `false` if the block is not modified in the running transaction.
+    pub fn read_block(&self, bid: Ext2Bid, buf: &mut [u8]) -> bool {
+        let inner = self.inner.lock();
+        let Some(block) = inner.blocks.get(&bid) else {
+            return false;
+        };
+        buf.copy_from_slice(block);
+        true
+    }
+
+    /// Checks if any of the blocks in `range` is modified in the running transaction.
+    pub fn contains_any(&self, range: Range<Ext2Bid>) -> bool {
+        self.inner.lock().blocks.range(range).next().is_some()
+    }
+
+    /// Drops the freed blocks from the running transaction.
+    ///
+    /// The blocks may be reused for file data, which must not be overwritten
+    /// when the transaction is checkpointed.
+    pub fn forget(&self, range: Range<Ext2Bid>) {
+        self.inner
+            .lock()
+            .blocks
+            .extract_if(range, |_, _| true)
+            .for_each(drop);
+    }
+
+    /// Commits and checkpoints the running transaction.
+    ///
+    /// The file data must have been written to the device before, so that the committed
+    /// metadata never refer to stale data.
+    pub fn commit(&self, block_device: &dyn BlockDevice) -> Result<()> {
+        let mut inner = self.inner.lock();
+        if inner.blocks.is_empty() {
+            return Ok(());
+        }
+
+        // The data must reach the device before the metadata are committed.
+        flush(block_device)?;
+
+        let blocks: Vec<(Ext2Bid, Box<[u8]>)> =
+            core::mem::take(&mut inner.blocks).into_iter().collect();
+        let capacity = self.max_transaction_blocks();
+        if blocks.len() > capacity {
+            warn!(
+                "{} blocks exceed the journal capacity, the transaction is split",
+                blocks.len()
+            );
+        }
+
+        let mut nr_committed = 0;
+        while nr_committed < blocks.len() {
+            let end = (nr_committed + capacity).min(blocks.len());
+            if let Err(err) =
+                self.commit_blocks(block_device, &mut inner, &blocks[nr_committed..end])
+            {
+                // Keeps the blocks that are not checkpointed in the running transaction.
+                inner.blocks.extend(blocks.into_iter().skip(nr_committed));
+                return Err(err);
+            }
+            nr_committed = end;
+        }
+
+        Ok(())
+    }
+
+    /// Commits and checkpoints the blocks as a transaction.
+    fn commit_blocks(
+        &self,
+        block_device: &dyn BlockDevice,
+        inner: &mut Inner,
+        blocks: &[(Ext2Bid, Box<[u8]>)],
+    ) -> Result<()> {
+        let sequence = inner.sequence;
+
+        // Writes the descriptor blocks and the metadata blocks to the log. The log blocks are
+        // contiguous, so plugging the queue allows them to be merged.
+        let plug = block_device.plug();
+        let mut bio_waiter = BioWaiter::new();
+        let mut log_idx = self.first;
+        for descriptor_blocks in blocks.chunks(self.tags_per_descriptor()) {
+            let descriptor_idx = log_idx;
+            log_idx += 1;
+
+            let mut descriptor = vec![0u8; BLOCK_SIZE];
+            JournalHeader::new(JournalBlockType::Descriptor, sequence).write(&mut descriptor);
+            let mut offset = size_of::<RawJournalHeader>();
+            for (i, (bid, block)) in descriptor_blocks.iter().enumerate() {
+                let mut flags = TagFlags::empty();
+                let mut log_block = block.clone();
+                // A block starting with the magic number is escaped,
+                // or it could be mistaken for a journal block.
+                if log_block[..4] == JOURNAL_MAGIC.to_be_bytes() {
+                    log_block[..4].fill(0);
+                    flags |= TagFlags::ESCAPE;
+                }
+                if i > 0 {
+                    flags |= TagFlags::SAME_UUID;
+                }
+                if i == descriptor_blocks.len() - 1 {
+                    flags |= TagFlags::LAST_TAG;
+                }
+
+                let checksum = self.tag_checksum(sequence, &log_block);
+                self.write_tag(&mut descriptor[offset..], *bid, flags, checksum);
+                offset += self.tag_size();
+                if i == 0 {
+                    descriptor[offset..offset + UUID_SIZE].copy_from_slice(&self.uuid);
+                    offset += UUID_SIZE;
+                }
+
+                bio_waiter.concat(self.write_log_block_async(block_device, log_idx, &log_block)?);
+                log_idx += 1;
+            }
+
+            self.set_tail_checksum(&mut descriptor);
+            bio_waiter.concat(self.write_log_block_async(
+                block_device,
+                descriptor_idx,
+                &descriptor,
+            )?);
+        }
+        // Points the journal to the transaction, which is valid only after the commit block
+        // is written.
+        bio_waiter.concat(self.write_super_block_async(block_device, inner, self.first)?);
+        drop(plug);
+        wait(bio_waiter, "failed to write the journal")?;
+
+        // Writes the commit block. The preflush makes the log blocks persistent before the
+        // commit block, and the FUA makes the commit block itself persistent.
+        let mut commit = vec![0u8; BLOCK_SIZE];
+        JournalHeader::new(JournalBlockType::Commit, sequence).write(&mut commit);
+        let commit_time = now();
+        commit[COMMIT_TIME_OFFSET..COMMIT_TIME_OFFSET + 8]
+            .copy_from_slice(&commit_time.as_secs().to_be_bytes());
+        commit[COMMIT_TIME_OFFSET + 8..COMMIT_TIME_OFFSET + 12]
+            .copy_from_slice(&commit_time.subsec_nanos().to_be_bytes());
+        if let Some(seed) = self.csum_seed {
+            let checksum = crc32c(seed, &commit);
+            commit[COMMIT_CHECKSUM_OFFSET..COMMIT_CHECKSUM_OFFSET + 4]
+                .copy_from_slice(&checksum.to_be_bytes());
+        }
+        let commit_offset = self.device_bid(log_idx) as usize * BLOCK_SIZE;
+        block_device
+            .write_bytes_with_flags(commit_offset, &commit, BioFlags::PREFLUSH | BioFlags::FUA)
+            .map_err(|_| Error::with_message(Errno::EIO, "failed to write the commit block"))?;
+
+        // Checkpoints the transaction.
+        let mut bio_waiter = BioWaiter::new();
+        for (bid, block) in blocks {
+            bio_waiter.concat(block_device.write_bytes_async(*bid as usize * BLOCK_SIZE, block)?);
+        }
+        wait(bio_waiter, "failed to checkpoint the journal")?;
+        flush(block_device)?;
+
+        // Empties the journal.
+        inner.sequence = sequence.wrapping_add(1);
+        self.write_super_block(block_device, inner, 0)?;
+        flush(block_device)?;
+
+        Ok(())
+    }
+
+    /// Replays the committed transactions in the log starting from the `start`-th block,
+    /// whose first transaction has the sequence number of `sequence`.
+    ///
+    /// Returns the sequence number of the next transaction.
+    fn recover(&self, block_device: &dyn BlockDevice, start: u32, sequence: u32) -> Result<u32> {
+        if start < self.first || start >= self.max_len {
+            return_errno_with_message!(Errno::EUCLEAN, "bad journal start");
+        }
+
+        // Scans the log for the committed transactions.
+        let mut sequence = sequence;
+        let mut log_idx = start;
+        let mut tags = Vec::new();
+        let mut revoked = BTreeMap::new();
+        let mut pending_tags = Vec::new();
+        let mut pending_revoked = Vec::new();
+        let mut buf = vec![0u8; BLOCK_SIZE];
+        for _ in self.first..self.max_len {
+            self.read_log_block(block_device, log_idx, &mut buf)?;
+            let header = JournalHeader::read(&buf);
+            if header.magic != JOURNAL_MAGIC || header.sequence != sequence {
+                break;
+            }
+
+            match header.block_type() {
+                Some(JournalBlockType::Descriptor) => {
+                    if !self.verify_tail_checksum(&buf) {
+                        break;
+                    }
+                    for tag in self.parse_tags(&buf)? {
+                        log_idx = self.next_log_idx(log_idx);
+                        pending_tags.push(ReplayTag {
+                            log_idx,
+                            sequence,
+                            ..tag
+                        });
+                    }
+                }
+                Some(JournalBlockType::Revoke) => {
+                    if !self.verify_tail_checksum(&buf) {
+                        break;
+                    }
+                    pending_revoked.extend(self.parse_revoke_records(&buf)?);
+                }
+                Some(JournalBlockType::Commit) => {
+                    if let Some(seed) = self.csum_seed {
+                        let checksum = u32::from_be_bytes(
+                            buf[COMMIT_CHECKSUM_OFFSET..COMMIT_CHECKSUM_OFFSET + 4]
+                                .try_into()
+                                .unwrap(),
+                        );
+                        buf[COMMIT_CHECKSUM_OFFSET..COMMIT_CHECKSUM_OFFSET + 4].fill(0);
+                        if crc32c(seed, &buf) != checksum {
+                            break;
+                        }
+                    }
+                    tags.append(&mut pending_tags);
+                    for bid in pending_revoked.drain(..) {
+                        revoked.insert(bid, sequence);
+                    }
+                    sequence = sequence.wrapping_add(1);
+                }
+                _ => break,
+            }
+            log_idx = self.next_log_idx(log_idx);
+        }
+
+        // Replays the blocks that are not revoked by the same or later transactions.
+        let mut bio_waiter = BioWaiter::new();
+        for tag in tags {
+            if revoked
+                .get(&tag.bid)
+                .is_some_and(|revoke_sequence| !sequence_after(tag.sequence, *revoke_sequence))
+            {
+                continue;
+            }
+
+            self.read_log_block(block_device, tag.log_idx, &mut buf)?;
+            if self.csum_seed.is_some() && !self.verify_tag_checksum(&tag, &buf) {
+                warn!("bad checksum of journal block {}", tag.log_idx);
+                continue;
+            }
+            if tag.flags.contains(TagFlags::ESCAPE) {
+                buf[..4].copy_from_slice(&JOURNAL_MAGIC.to_be_bytes());
+            }
+            bio_waiter.concat(block_device.write_bytes_async(tag.bid as usize * BLOCK_SIZE, &buf)?);
+        }
+        wait(bio_waiter, "failed to replay the journal")?;
+        flush(block_device)?;
+
+        // Skips the sequence number of the incomplete transaction, if any.
+        Ok(sequence.wrapping_add(1))
+    }
+
+    /// Returns the maximal number of blocks committed in a transaction.
+    fn max_transaction_blocks(&self) -> usize {
+        let tags_per_descriptor = self.tags_per_descriptor();
+        // Each descriptor block is followed by at most `tags_per_descriptor` blocks,
+        // and the transaction ends with a commit block.
+        let log_len = (self.max_len - self.first) as usize;
+        (log_len - 1) / (tags_per_descriptor + 1) * tags_per_descriptor
+    }
+
+    /// Returns the number of tags that a descriptor block can hold.
+    fn tags_per_descriptor(&self) -> usize {
+        let header_size = size_of::<RawJournalHeader>();
+        (BLOCK_SIZE - header_size - UUID_SIZE - self.tail_size()) / self.tag_size()
+    }
+
+    /// Returns the size of a tag in descriptor blocks.
+    fn tag_size(&self) -> usize {
+        if self
+            .feature_incompat
+            .contains(JournalFeatureInCompatSet::CSUM_V3)
+        {
+            return 16;
+        }
+        let mut size = 12;
+        if self
+            .feature_incompat
+            .contains(JournalFeatureInCompatSet::CSUM_V2)
+        {
+            size += 2;
+        }
+        if !self
+            .feature_incompat
+            .contains(JournalFeatureInCompatSet::IS_64BIT)
+        {
+            size -= 4;
+        }
+        size
+    }
+
+    /// Returns the size of the checksum tail in descriptor and revoke blocks.
+    fn tail_size(&self) -> usize {
+        if self.csum_seed.is_some() { 4 } else { 0 }
+    }
+
+    /// Writes a tag to the beginning of `buf`.
+    fn write_tag(&self, buf: &mut [u8], bid: Ext2Bid, flags: TagFlags, checksum: u32) {
+        buf[..4].copy_from_slice(&bid.to_be_bytes());
+        if self
+            .feature_incompat
+            .contains(JournalFeatureInCompatSet::CSUM_V3)
+        {
+            buf[4..8].copy_from_slice(&(flags.bits() as u32).to_be_bytes());
+            buf[12..16].copy_from_slice(&checksum.to_be_bytes());
+        } else {
+            buf[4..6].copy_from_slice(&(checksum as u16).to_be_bytes());
+            buf[6..8].copy_from_slice(&flags.bits().to_be_bytes());
+        }
+        // The high 32 bits of the block ID, if any, are always zero.
+    }
+
+    /// Parses the tags in a descriptor block.
+    fn parse_tags(&self, buf: &[u8]) -> Result<Vec<ReplayTag>> {
+        let is_csum_v3 = self
+            .feature_incompat
+            .contains(JournalFeatureInCompatSet::CSUM_V3);
+        let is_64bit = self
+            .feature_incompat
+            .contains(JournalFeatureInCompatSet::IS_64BIT);
+        let end = BLOCK_SIZE - self.tail_size();
+
+        let mut tags = Vec::new();
+        let mut offset = size_of::<RawJournalHeader>();
+        while offset + self.tag_size() <= end {
+            let tag = &buf[offset..offset + self.tag_size()];
+            let be32 =
+                |offset: usize| u32::from_be_bytes(tag[offset..offset + 4].try_into().unwrap());
+            let bid_high = if is_64bit || is_csum_v3 { be32(8) } else { 0 };
+            // The low 16 bits of the flags are at the same offset in all the formats.
+            let flags = TagFlags::from_bits_truncate(u16::from_be_bytes([tag[6], tag[7]]));
+            let checksum = if is_csum_v3 {
+                be32(12)
+            } else {
+                u16::from_be_bytes([tag[4], tag[5]]) as u32
+            };
+
+            if bid_high != 0 {
+                return_errno_with_message!(Errno::EUCLEAN, "the journal block is out of bounds");
+            }
+            tags.push(ReplayTag {
+                bid: be32(0),
+                flags,
+                checksum,
+                log_idx: 0,
+                sequence: 0,
+            });
+
+            offset += self.tag_size();
+            if !flags.contains(TagFlags::SAME_UUID) {
+                offset += UUID_SIZE;
+            }
+            if flags.contains(TagFlags::LAST_TAG) {
+                break;
+            }
+        }
+
+        Ok(tags)
+    }
+
+    /// Parses the block IDs in a revoke block.
+    fn parse_revoke_records(&self, buf: &[u8]) -> Result<Vec<Ext2Bid>> {
+        let count = u32::from_be_bytes(
+            buf[REVOKE_COUNT_OFFSET..REVOKE_COUNT_OFFSET + 4]
+                .try_into()
+                .unwrap(),
+        ) as usize;
+        if count > BLOCK_SIZE - self.tail_size() || count < REVOKE_HEADER_SIZE {
+            return_errno_with_message!(Errno::EUCLEAN, "bad journal revoke block");
+        }
+
+        let record_size = if self
+            .feature_incompat
+            .contains(JournalFeatureInCompatSet::IS_64BIT)
+        {
+            8
+        } else {
+            4
+        };
+        let records = buf[REVOKE_HEADER_SIZE..count]
+            .chunks_exact(record_size)
+            .filter_map(|record| {
+                let mut bid = [0u8; 8];
+                bid[8 - record_size..].copy_from_slice(record);
+                Ext2Bid::try_from(u64::from_be_bytes(bid)).ok()
+            })
+            .collect();
+        Ok(records)
+    }
+
+    /// Computes the checksum of a journal block with the sequence number of its transaction.
+    fn tag_checksum(&self, sequence: u32, block: &[u8]) -> u32 {
+        let Some(seed) = self.csum_seed else {
+            return 0;
+        };
+        let checksum = crc32c(seed, &sequence.to_be_bytes());
+        crc32c(checksum, block)
+    }
+
+    /// Verifies the checksum in the tag of a journal block.
+    fn verify_tag_checksum(&self, tag: &ReplayTag, block: &[u8]) -> bool {
+        let checksum = self.tag_checksum(tag.sequence, block);
+        if self
+            .feature_incompat
+            .contains(JournalFeatureInCompatSet::CSUM_V3)
+        {
+            checksum == tag.checksum
+        } else {
+            checksum as u16 == tag.checksum as u16
+        }
+    }
+
+    /// Sets the checksum tail of a descriptor or revoke block.
+    fn set_tail_checksum(&self, block: &mut [u8]) {
+        let Some(seed) = self.csum_seed else {
+            return;
+        };
+        block[BLOCK_SIZE - 4..].fill(0);
+        let checksum = crc32c(seed, block);
+        block[BLOCK_SIZE - 4..].copy_from_slice(&checksum.to_be_bytes());
+    }
+
+    /// Verifies the checksum tail of a descriptor or revoke block.
+    fn verify_tail_checksum(&self, block: &[u8]) -> bool {
+        let Some(seed) = self.csum_seed else {
+            return true;
+        };
+        let (data, tail) = block.split_at(BLOCK_SIZE - 4);
+        let checksum = crc32c(crc32c(seed, data), &[0u8; 4]);
+        checksum.to_be_bytes() == tail
+    }
+
+    /// Returns the index of the log block after the `log_idx`-th one.
+    fn next_log_idx(&self, log_idx: u32) -> u32 {
+        if log_idx + 1 >= self.max_len {
+            self.first
+        } else {
+            log_idx + 1
+        }
+    }
+
+    /// Returns the device block ID of the `log_idx`-th block of the journal.
+    fn device_bid(&self, log_idx: u32) -> Ext2Bid {
+        let mut idx = log_idx;
+        for range in self.device_ranges.iter() {
+            let len = range.len() as u32;
+            if idx < len {
+                return range.start + idx;
+            }
+            idx -= len;
+        }
+        unreachable!("the index of the journal block is out of bounds");
+    }
+
+    fn read_log_block(
+        &self,
+        block_device: &dyn BlockDevice,
+        log_idx: u32,
+        buf: &mut [u8],
+    ) -> Result<()> {
+        let bid = self.device_bid(log_idx);
+        block_device.read_bytes(bid as usize * BLOCK_SIZE, buf)?;
+        Ok(())
+    }
+
+    fn write_log_block_async(
+        &self,
+        block_device: &dyn BlockDevice,
+        log_idx: u32,
+        buf: &[u8],
+    ) -> Result<BioWaiter> {
+        let bid = self.device_bid(log_idx);
+        let bio_waiter = block_device.write_bytes_async(bid as usize * BLOCK_SIZE, buf)?;
+        Ok(bio_waiter)
+    }
+
+    /// Writes the journal superblock with the sequence number of the running transaction
+    /// and the log start of `start`, which is zero if the journal is empty.
+    fn write_super_block_async(
+        &self,
+        block_device: &dyn BlockDevice,
+        inner: &mut Inner,
+        start: u32,
+    ) -> Result<BioWaiter> {
+        let mut raw_super_block = RawJournalSuperBlock::from_first_bytes(&inner.super_block);
+        raw_super_block.sequence = inner.sequence.to_be();
+        raw_super_block.start = start.to_be();
+        inner.super_block[..size_of::<RawJournalSuperBlock>()]
+            .copy_from_slice(raw_super_block.as_bytes());
+        if self.csum_seed.is_some() {
+            raw_super_block.checksum = super_block_checksum(&inner.super_block).to_be();
+            inner.super_block[..size_of::<RawJournalSuperBlock>()]
+                .copy_from_slice(raw_super_block.as_bytes());
+        }
+
+        self.write_log_block_async(block_device, 0, &inner.super_block)
+    }
+
+    fn write_super_block(
+        &self,
+        block_device: &dyn BlockDevice,
+        inner: &mut Inner,
+        start: u32,
+    ) -> Result<()> {
+        let bio_waiter = self.write_super_block_async(block_device, inner, start)?;
+        wait(bio_waiter, "failed to write the journal superblock")
+    }
+}
+
+impl Debug for Journal {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("Journal")
+            .field("device_ranges", &self.device_ranges)
+            .field("first", &self.first)
+            .field("max_len", &self.max_len)
+            .field("feature_incompat", &self.feature_incompat)
+            .finish()
+    }
+}
+
+/// A tag of a journal block to replay.
+#[derive(Clone, Copy, Debug)]
+struct ReplayTag {
+    /// The home location of the block.
+    bid: Ext2Bid,
+    flags: TagFlags,
+    checksum: u32,
+    /// The index of the block in the journal.
+    log_idx: u32,
+    /// The sequence number of the transaction.
+    sequence: u32,
+}
+
+/// Reads the raw inode of the journal.
+fn read_journal_inode(
+    block_device: &dyn BlockDevice,
+    super_block: &SuperBlock,
+) -> Result<RawInode> {
+    let ino = super_block.journal_ino();
+    if ino == 0 || ino > super_block.total_inodes() {
+        return_errno_with_message!(Errno::EINVAL, "unsupported journal inode");
+    }
+    let block_group_idx = ((ino - 1) / super_block.inodes_per_group()) as usize;
+    let inode_idx = ((ino - 1) % super_block.inodes_per_group()) as usize;
+
+    let mut raw_descriptor = RawGroupDescriptor::new_zeroed();
+    let desc_size = super_block.desc_size();
+    block_device.read_bytes(
+        super_block.group_descriptors_bid(0).to_offset() + block_group_idx * desc_size,
+        &mut raw_descriptor.as_mut_bytes()[..desc_size],
+    )?;
+
+    let offset =
+        raw_descriptor.inode_table as usize * BLOCK_SIZE + inode_idx * super_block.inode_size();
+    let mut buf = vec![0u8; BLOCK_SIZE];
+    block_device.read_bytes(offset.align_down(BLOCK_SIZE), &mut buf)?;
+    Ok(RawInode::from_first_bytes(&buf[offset % BLOCK_SIZE..]))
+}
+
+/// Maps the `nblocks` blocks of the journal inode to the device ranges.
+fn map_journal_blocks(
+    block_device: &dyn BlockDevice,
+    raw_inode: &RawInode,
+    nblocks: Ext2Bid,
+) -> Result<Vec<Range<Ext2Bid>>> {
+    let mut device_ranges = Vec::new();
+
+    if raw_inode.flags & FileFlags::EXTENTS.bits() != 0 {
+        let read_block = |bid: Ext2Bid, buf: &mut [u8]| -> Result<()> {
+            block_device.read_bytes(bid as usize * BLOCK_SIZE, buf)?;
+            Ok(())
+        };
+        let extent_tree = ExtentTree::load(&raw_inode.block_ptrs, &read_block, None)?;
+        let mut current = 0;
+        while current < nblocks {
+            let ExtentMapping::Mapped(device_range) =
+                extent_tree.lookup(current, nblocks - current)
+            else {
+                return_errno_with_message!(Errno::EUCLEAN, "the journal has holes");
+            };
+            current += device_range.len() as Ext2Bid;
+            push_device_range(&mut device_ranges, device_range);
+        }
+        return Ok(device_ranges);
+    }
+
+    let block_ptrs: &BlockPtrs = &raw_inode.block_ptrs;
+    let mut remaining = nblocks;
+    for idx in DIRECT_RANGE {
+        map_indirect_blocks(
+            block_device,
+            block_ptrs.direct(idx),
+            0,
+            &mut remaining,
+            &mut device_ranges,
+        )?;
+    }
+    for (level, bid) in [
+        (1, block_ptrs.indirect()),
+        (2, block_ptrs.db_indirect()),
+        (3, block_ptrs.tb_indirect()),
+    ] {
+        map_indirect_blocks(block_device, bid, level, &mut remaining, &mut device_ranges)?;
+    }
+    Ok(device_ranges)
+}
+
+/// Maps the blocks pointed by the block of `bid` with the indirection of `level`.
+fn map_indirect_blocks(
+    block_device: &dyn BlockDevice,
+    bid: Ext2Bid,
+    level: usize,
+    remaining: &mut Ext2Bid,
+    device_ranges: &mut Vec<Range<Ext2Bid>>,
+) -> Result<()> {
+    if *remaining == 0 {
+        return Ok(());
+    }
+    if bid == 0 {
+        return_errno_with_message!(Errno::EUCLEAN, "the journal has holes");
+    }
+    if level == 0 {
+        push_device_range(device_ranges, bid..bid + 1);
+        *remaining -= 1;
+        return Ok(());
+    }
+
+    let mut buf = vec![0u8; BLOCK_SIZE];
+    block_device.read_bytes(bid as usize * BLOCK_SIZE, &mut buf)?;
+    for bid_bytes in buf.chunks_exact(BID_SIZE) {
+        let bid = Ext2Bid::from_first_bytes(bid_bytes);
+        map_indirect_blocks(block_device, bid, level - 1, remaining, device_ranges)?;
+        if *remaining == 0 {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Appends `device_range`, merging it with the last range if they are consecutive.
+fn push_device_range(device_ranges: &mut Vec<Range<Ext2Bid>>, device_range: Range<Ext2Bid>) {
+    match device_ranges.last_mut() {
+        Some(last) if last.end == device_range.start => last.end = device_range.end,
+        _ => device_ranges.push(device_range),
+    }
+}
+
+/// Computes the checksum of the journal superblock in `block`.
+fn super_block_checksum(block: &[u8]) -> u32 {
+    let mut buf = block[..JOURNAL_SUPER_BLOCK_SIZE].to_vec();
+    let checksum_offset = core::mem::offset_of!(RawJournalSuperBlock, checksum);
+    buf[checksum_offset..checksum_offset + 4].fill(0);
+    crc32c(!0, &buf)
+}
+
+/// Checks if the sequence number `a` is after `b`, considering the wrapping.
+fn sequence_after(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) > 0
+}
+
+fn flush(block_device: &dyn BlockDevice) -> Result<()> {
+    match block_device.sync()? {
+        BioStatus::Complete => Ok(()),
+        err_status => Err(Error::from(err_status)),
+    }
+}
+
+fn wait(bio_waiter: BioWaiter, message: &'static str) -> Result<()> {
+    match bio_waiter.wait() {
+        Some(BioStatus::Complete) => Ok(()),
+        _ => Err(Error::with_message(Errno::EIO, message)),
+    }
+}
+
+/// The types of the journal blocks.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, TryFromInt)]
+enum JournalBlockType {
+    Descriptor = 1,
+    Commit = 2,
+    SuperBlockV1 = 3,
+    SuperBlockV2 = 4,
+    Revoke = 5,
+}
+
+/// The header of the journal blocks.
+#[derive(Clone, Copy, Debug)]
+struct JournalHeader {
+    magic: u32,
+    block_type: u32,
+    sequence: u32,
+}
+
+impl JournalHeader {
+    fn new(block_type: JournalBlockType, sequence: u32) -> Self {
+        Self {
+            magic: JOURNAL_MAGIC,
+            block_type: block_type as u32,
+            sequence,
+        }
+    }
+
+    /// Returns the block type, or `None` if it is unknown.
+    fn block_type(&self) -> Option<JournalBlockType> {
+        JournalBlockType::try_from(self.block_type).ok()
+    }
+
+    fn read(block: &[u8]) -> Self {
+        RawJournalHeader::from_first_bytes(block).parse()
+    }
+
+    fn write(&self, block: &mut [u8]) {
+        let raw_header = RawJournalHeader {
+            magic: self.magic.to_be(),
+            block_type: self.block_type.to_be(),
+            sequence: self.sequence.to_be(),
+        };
+        block[..size_of::<RawJournalHeader>()].copy_from_slice(raw_header.as_bytes());
+    }
+}
+
+/// The on-disk header of the journal blocks, whose fields are big-endian.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+struct RawJournalHeader {
+    magic: u32,
+    block_type: u32,
+    sequence: u32,
+}
+
+impl RawJournalHeader {
+    fn parse(&self) -> JournalHeader {
+        JournalHeader {
+            magic: u32::from_be(self.magic),
+            block_type: u32::from_be(self.block_type),
+            sequence: u32::from_be(self.sequence),
+        }
+    }
+}
+
+/// The on-disk journal superblock, whose fields are big-endian.
+///
+/// The IDs of the filesystems sharing the journal follow the structure,
+/// which are unused for the internal journal.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+struct RawJournalSuperBlock {
+    header: RawJournalHeader,
+    /// The size of the journal blocks.
+    block_size: u32,
+    /// The number of blocks in the journal.
+    max_len: u32,
+    /// The index of the first block of the log.
+    first: u32,
+    /// The sequence number of the first transaction in the log.
+    sequence: u32,
+    /// The index of the first block of the first transaction in the log,
+    /// which is zero if the journal is empty.
+    start: u32,
+    /// The error number set when the journal is aborted.
+    errno: u32,
+    feature_compat: u32,
+    feature_incompat: u32,
+    feature_ro_compat: u32,
+    uuid: [u8; 16],
+    /// The number of filesystems sharing the journal.
+    nr_users: u32,
+    /// The location of the dynamic superblock copy, which is unused.
+    dyn_super: u32,
+    /// The limit of the blocks per transaction, which is unused.
+    max_transaction: u32,
+    /// The limit of the data blocks per transaction, which is unused.
+    max_trans_data: u32,
+    checksum_type: u8,
+    padding2: [u8; 3],
+    /// The number of fast commit blocks in the journal.
+    num_fc_blocks: u32,
+    /// The index of the journal head, which is unused.
+    head: u32,
+    padding: [u32; 40],
+    checksum: u32,
+}
+
+bitflags! {
+    /// Incompatible feature set of the journal.
+    struct JournalFeatureInCompatSet: u32 {
+        /// Revoke blocks are supported
+        const REVOKE = 1 << 0;
+        /// The block IDs can be 64-bit
+        const IS_64BIT = 1 << 1;
+        /// The commit block can be written without waiting for the descriptor blocks
+        const ASYNC_COMMIT = 1 << 2;
+        /// Journal blocks have version 2 checksums
+        const CSUM_V2 = 1 << 3;
+        /// Journal blocks have version 3 checksums
+        const CSUM_V3 = 1 << 4;
+        /// Fast commits are supported
+        const FAST_COMMIT = 1 << 5;
+    }
+}
+
+impl JournalFeatureInCompatSet {
+    /// The features that are not supported.
+    const UNSUPPORTED: Self =
+        Self::from_bits_truncate(Self::ASYNC_COMMIT.bits | Self::FAST_COMMIT.bits);
+}
+
+bitflags! {
+    /// The flags of the tags in descriptor blocks.
+    struct TagFlags: u16 {
+        /// The first four bytes of the block are replaced with zeros
+        const ESCAPE = 1 << 0;
+        /// The UUID is the same as the previous tag's, so it is omitted
+        const SAME_UUID = 1 << 1;
+        /// The block is deleted by this transaction
+        const DELETED = 1 << 2;
+        /// The last tag in the descriptor block
+        const LAST_TAG = 1 << 3;
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use aster_block::{
+        BlockDeviceMeta,
+        bio::{BioEnqueueError, BioType, SubmittedBio},
+    };
+    use device_id::DeviceId;
+    use ostd::{
+        mm::{PAGE_SIZE, io::util::HasVmReaderWriter},
+        prelude::*,
+    };
+
+    use super::*;
+    use crate::fs::ext2::{
+        Ext2, MAGIC_NUM,
+        super_block::{FeatureCompatSet, FeatureInCompatSet, FsState, RawSuperBlock},
+    };
+
+    /// A block device whose blocks are kept in memory.
+    struct JournalMemoryDisk(Segment<()>);
+
+    impl Debug for JournalMemoryDisk {
+        fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+            f.debug_struct("JournalMemoryDisk")
+                .field("blocks_count", &(self.0.size() / BLOCK_SIZE))
+                .finish()
+        }
+    }
+
+    impl BlockDevice for JournalMemoryDisk {
+        fn enqueue(&self, bio: SubmittedBio) -> core::prelude::v1::Result<(), BioEnqueueError> {
+            let mut cur_device_ofs = bio.sid_range().start.to_raw() as usize * SECTOR_SIZE;
+            for seg in bio.segments() {
+                let size = match bio.type_() {
+                    BioType::Read => seg
+                        .inner_dma()
+                        .writer()
+                        .unwrap()
+                        .write(self.0.reader().skip(cur_device_ofs)),
+                    BioType::Write => self
+                        .0
+                        .writer()
+                        .skip(cur_device_ofs)
+                        .write(&mut seg.inner_dma().reader().unwrap()),
+                    _ => 0,
+                };
+                cur_device_ofs += size;
+            }
+            bio.complete(BioStatus::Complete);
+            Ok(())
+        }
+
+        fn metadata(&self) -> BlockDeviceMeta {
+            BlockDeviceMeta {
+                max_nr_segments_per_bio: usize::MAX,
+                nr_sectors: self.0.size() / SECTOR_SIZE,
+                supports_fua: false,
+                max_discard_sectors: 0,
+            }
+        }
+
+        fn name(&self) -> &str {
+            todo!()
+        }
+
+        fn id(&self) -> DeviceId {
+            todo!()
+        }
+    }
+
+    // The layout of the Ext2 image with a single block group. The blocks between
+    // `HOME_BLOCK` and `JOURNAL_BLOCK` are the home locations of the journaled blocks.
+    const NR_BLOCKS: usize = 1088;
+    const NR_INODES: u32 = 32;
+    const FIRST_INO: u32 = 11;
+    const JOURNAL_INO: u32 = 8;
+    const GDT_BLOCK: usize = 1;
+    const BLOCK_BITMAP_BLOCK: usize = 2;
+    const INODE_BITMAP_BLOCK: usize = 3;
+    const INODE_TABLE_BLOCK: usize = 4;
+    const ROOT_DIR_BLOCK: usize = 5;
+    const JOURNAL_INDIRECT_BLOCK: usize = 6;
+    const HOME_BLOCK: Ext2Bid = 8;
+    const JOURNAL_BLOCK: usize = 16;
+    const JOURNAL_LEN: usize = 1024;
+    const NR_USED_BLOCKS: usize = JOURNAL_BLOCK + JOURNAL_LEN;
+
+    /// The sequence number of the first transaction in the log.
+    const SEQUENCE: u32 = 7;
+
+    /// Formats a disk with an empty root directory and an empty journal.
+    ///
+    /// If `needs_recovery` is true, the filesystem is marked as not unmounted cleanly.
+    fn format(needs_recovery: bool) -> Arc<JournalMemoryDisk> {
+        let segment = FrameAllocOptions::new()
+            .alloc_segment(NR_BLOCKS * BLOCK_SIZE / PAGE_SIZE)
+            .unwrap();
+
+        let mut feature_incompat = FeatureInCompatSet::empty();
+        if needs_recovery {
+            feature_incompat.insert(FeatureInCompatSet::RECOVER);
+        }
+        let raw_super_block = RawSuperBlock {
+            inodes_count: NR_INODES,
+            blocks_count: NR_BLOCKS as u32,
+            free_blocks_count: (NR_BLOCKS - NR_USED_BLOCKS) as u32,
+            free_inodes_count: NR_INODES - FIRST_INO,
+            log_block_size: 2,
+            log_frag_size: 2,
+            blocks_per_group: (BLOCK_SIZE * 8) as u32,
+            frags_per_group: (BLOCK_SIZE * 8) as u32,
+            inodes_per_group: NR_INODES,
+            max_mnt_count: u16::MAX,
+            magic: MAGIC_NUM,
+            state: FsState::VALID.bits(),
+            errors: 1,
+            rev_level: 1,
+            first_ino: FIRST_INO,
+            inode_size: 128,
+            feature_compat: FeatureCompatSet::HAS_JOURNAL.bits(),
+            feature_incompat: feature_incompat.bits(),
+            journal_ino: JOURNAL_INO,
+            ..Default::default()
+        };
+        segment.write_val(1024, &raw_super_block).unwrap();
+
+        let mut raw_descriptor = RawGroupDescriptor::new_zeroed();
+        raw_descriptor.block_bitmap = BLOCK_BITMAP_BLOCK as u32;
+        raw_descriptor.inode_bitmap = INODE_BITMAP_BLOCK as u32;
+        raw_descriptor.inode_table = INODE_TABLE_BLOCK as u32;
+        raw_descriptor.free_blocks_count = (NR_BLOCKS - NR_USED_BLOCKS) as u16;
+        raw_descriptor.free_inodes_count = (NR_INODES - FIRST_INO) as u16;
+        raw_descriptor.dirs_count = 1;
+        segment
+            .write_val(GDT_BLOCK * BLOCK_SIZE, &raw_descriptor)
+            .unwrap();
+
+        // The bits beyond the end of the group are marked as used.
+        let write_bitmap = |bid: usize, nr_used: usize, nr_total: usize| {
+            let mut bitmap = vec![0u8; BLOCK_SIZE];
+            for bit in (0..nr_used).chain(nr_total..BLOCK_SIZE * 8) {
+                bitmap[bit / 8] |= 1 << (bit % 8);
+            }
+            segment.write_bytes(bid * BLOCK_SIZE, &bitmap).unwrap();
+        };
+        write_bitmap(BLOCK_BITMAP_BLOCK, NR_USED_BLOCKS, NR_BLOCKS);
+        write_bitmap(INODE_BITMAP_BLOCK, FIRST_INO as usize, NR_INODES as usize);
+
+        let write_inode = |ino: u32, raw_inode: &RawInode| {
+            let offset = INODE_TABLE_BLOCK * BLOCK_SIZE + (ino as usize - 1) * 128;
+            segment.write_val(offset, raw_inode).unwrap();
+        };
+
+        let mut root = RawInode {
+            mode: InodeType::Dir as u16 | 0o755,
+            size_low: BLOCK_SIZE as u32,
+            hard_links: 2,
+            sector_count: (BLOCK_SIZE / SECTOR_SIZE) as u32,
+            ..Default::default()
+        };
+        root.block_ptrs.set_direct(0, ROOT_DIR_BLOCK as Ext2Bid);
+        write_inode(2, &root);
+
+        let mut dir_block = vec![0u8; BLOCK_SIZE];
+        dir_block[..12].copy_from_slice(&[2, 0, 0, 0, 12, 0, 1, 2, b'.', 0, 0, 0]);
+        dir_block[12..20].copy_from_slice(&[2, 0, 0, 0, 0xf4, 0x0f, 2, 2]);
+        dir_block[20..22].copy_from_slice(b"..");
+        segment
+            .write_bytes(ROOT_DIR_BLOCK * BLOCK_SIZE, &dir_block)
+            .unwrap();
+
+        // The journal is mapped by the direct blocks and an indirect block.
+        let mut journal = RawInode {
+            mode: InodeType::File as u16 | 0o600,
+            size_low: (JOURNAL_LEN * BLOCK_SIZE) as u32,
+            hard_links: 1,
+            sector_count: ((JOURNAL_LEN + 1) * BLOCK_SIZE / SECTOR_SIZE) as u32,
+            ..Default::default()
+        };
+        for idx in DIRECT_RANGE {
+            journal
+                .block_ptrs
+                .set_direct(idx, (JOURNAL_BLOCK + idx) as Ext2Bid);
+        }
+        journal
+            .block_ptrs
+            .set_indirect(JOURNAL_INDIRECT_BLOCK as Ext2Bid);
+        write_inode(JOURNAL_INO, &journal);
+
+        let indirect_block: Vec<u8> = (JOURNAL_BLOCK + DIRECT_RANGE.end
+            ..JOURNAL_BLOCK + JOURNAL_LEN)
+            .flat_map(|bid| (bid as Ext2Bid).to_le_bytes())
+            .collect();
+        segment
+            .write_bytes(JOURNAL_INDIRECT_BLOCK * BLOCK_SIZE, &indirect_block)
+            .unwrap();
+
+        let mut raw_journal_super_block = RawJournalSuperBlock::new_zeroed();
+        raw_journal_super_block.header = RawJournalHeader {
+            magic: JOURNAL_MAGIC.to_be(),
+            block_type: (JournalBlockType::SuperBlockV2 as u32).to_be(),
+            sequence: 0,
+        };
+        raw_journal_super_block.block_size = (BLOCK_SIZE as u32).to_be();
+        raw_journal_super_block.max_len = (JOURNAL_LEN as u32).to_be();
+        raw_journal_super_block.first = 1u32.to_be();
+        raw_journal_super_block.sequence = SEQUENCE.to_be();
+        raw_journal_super_block.feature_incompat = JournalFeatureInCompatSet::REVOKE.bits().to_be();
+        segment
+            .write_val(JOURNAL_BLOCK * BLOCK_SIZE, &raw_journal_super_block)
+            .unwrap();
+
+        Arc::new(JournalMemoryDisk(segment))
+    }
+
+    /// Writes the `blocks` to the log, starting from the first log block.
+    fn write_log(disk: &JournalMemoryDisk, blocks: &[Vec<u8>]) {
+        for (idx, block) in blocks.iter().enumerate() {
+            let offset = (JOURNAL_BLOCK + 1 + idx) * BLOCK_SIZE;
+            disk.0.write_bytes(offset, block).unwrap();
+        }
+
+        let offset =
+            JOURNAL_BLOCK * BLOCK_SIZE + core::mem::offset_of!(RawJournalSuperBlock, start);
+        disk.0.write_val(offset, &1u32.to_be()).unwrap();
+    }
+
+    fn read_journal_super_block(disk: &JournalMemoryDisk) -> RawJournalSuperBlock {
+        disk.0.read_val(JOURNAL_BLOCK * BLOCK_SIZE).unwrap()
+    }
+
+    fn read_block(disk: &JournalMemoryDisk, bid: Ext2Bid) -> Vec<u8> {
+        let mut block = vec![0u8; BLOCK_SIZE];
+        disk.0
+            .read_bytes(bid as usize * BLOCK_SIZE, &mut block)
+            .unwrap();
+        block
+    }
+
+    fn descriptor_block(sequence: u32, tags: &[(Ext2Bid, TagFlags)]) -> Vec<u8> {
+        let mut block = vec![0u8; BLOCK_SIZE];
+        JournalHeader::new(JournalBlockType::Descriptor, sequence).write(&mut block);
+        let mut offset = size_of::<RawJournalHeader>();
+        for (idx, (bid, flags)) in tags.iter().enumerate() {
+            let mut flags = *flags;
+            if idx > 0 {
+                flags |= TagFlags::SAME_UUID;
+            }
+            if idx == tags.len() - 1 {
+                flags |= TagFlags::LAST_TAG;
+            }
+            block[offset..offset + 4].copy_from_slice(&bid.to_be_bytes());
+            block[offset + 6..offset + 8].copy_from_slice(&flags.bits().to_be_bytes());
+            offset += 8;
+            if idx == 0 {
+                offset += UUID_SIZE;
+            }
+        }
+        block
+    }
+
+    fn revoke_block(sequence: u32, bids: &[Ext2Bid]) -> Vec<u8> {
+        let mut block = vec![0u8; BLOCK_SIZE];
+        JournalHeader::new(JournalBlockType::Revoke, sequence).write(&mut block);
+        let count = REVOKE_HEADER_SIZE + bids.len() * BID_SIZE;
+        block[REVOKE_COUNT_OFFSET..REVOKE_COUNT_OFFSET + 4]
+            .copy_from_slice(&(count as u32).to_be_bytes());
+        for (record, bid) in block[REVOKE_HEADER_SIZE..count]
+            .chunks_exact_mut(BID_SIZE)
+            .zip(bids)
+        {
+            record.copy_from_slice(&bid.to_be_bytes());
+        }
+        block
+    }
+
+    fn commit_block(sequence: u32) -> Vec<u8> {
+        let mut block = vec![0u8; BLOCK_SIZE];
+        JournalHeader::new(JournalBlockType::Commit, sequence).write(&mut block);
+        block
+    }
+
+    /// Returns a block starting with the journal magic number, which must be escaped.
+    fn magic_block() -> Vec<u8> {
+        let mut block = vec![0xd1u8; BLOCK_SIZE];
+        block[..4].copy_from_slice(&JOURNAL_MAGIC.to_be_bytes());
+        block
+    }
+
+    /// Writes two committed transactions and an uncommitted one to the log.
+    ///
+    /// The first transaction writes `HOME_BLOCK`, `HOME_BLOCK + 2` and `HOME_BLOCK + 3`.
+    /// The second one overwrites `HOME_BLOCK` and revokes `HOME_BLOCK + 2`. The
+    /// uncommitted one writes `HOME_BLOCK + 1` and overwrites `HOME_BLOCK` again.
+    fn write_transactions(disk: &JournalMemoryDisk) {
+        let mut escaped_block = magic_block();
+        escaped_block[..4].fill(0);
+
+        write_log(
+            disk,
+            &[
+                descriptor_block(
+                    SEQUENCE,
+                    &[
+                        (HOME_BLOCK, TagFlags::empty()),
+                        (HOME_BLOCK + 2, TagFlags::empty()),
+                        (HOME_BLOCK + 3, TagFlags::ESCAPE),
+                    ],
+                ),
+                vec![0xa1u8; BLOCK_SIZE],
+                vec![0xc1u8; BLOCK_SIZE],
+                escaped_block,
+                commit_block(SEQUENCE),
+                descriptor_block(SEQUENCE + 1, &[(HOME_BLOCK, TagFlags::empty())]),
+                vec![0xa2u8; BLOCK_SIZE],
+                revoke_block(SEQUENCE + 1, &[HOME_BLOCK + 2]),
+                commit_block(SEQUENCE + 1),
+                descriptor_block(
+                    SEQUENCE + 2,
+                    &[
+                        (HOME_BLOCK + 1, TagFlags::empty()),
+                        (HOME_BLOCK, TagFlags::empty()),
+                    ],
+                ),
+                vec![0xb1u8; BLOCK_SIZE],
+                vec![0xffu8; BLOCK_SIZE],
+            ],
+        );
+    }
+
+    #[ktest]
+    fn replay_committed_transactions() {
+        let disk = format(true);
+        write_transactions(&disk);
+        let _ext2 = Ext2::open(disk.clone()).unwrap();
+
+        assert_eq!(read_block(&disk, HOME_BLOCK), vec![0xa2u8; BLOCK_SIZE]);
+        // The block of the uncommitted transaction is not replayed.
+        assert_eq!(read_block(&disk, HOME_BLOCK + 1), vec![0u8; BLOCK_SIZE]);
+        // The block revoked by a later transaction is not replayed.
+        assert_eq!(read_block(&disk, HOME_BLOCK + 2), vec![0u8; BLOCK_SIZE]);
+        assert_eq!(read_block(&disk, HOME_BLOCK + 3), magic_block());
+
+        // The journal is emptied, and the sequence number of the uncommitted
+        // transaction is skipped.
+        let raw_journal_super_block = read_journal_super_block(&disk);
+        assert_eq!(u32::from_be(raw_journal_super_block.start), 0);
+        assert_eq!(u32::from_be(raw_journal_super_block.sequence), SEQUENCE + 3);
+    }
+
+    #[ktest]
+    fn discard_without_recovery() {
+        let disk = format(false);
+        write_transactions(&disk);
+        let _ext2 = Ext2::open(disk.clone()).unwrap();
+
+        // The filesystem is unmounted cleanly, so the journal is discarded.
+        for bid in HOME_BLOCK..HOME_BLOCK + 4 {
+            assert_eq!(read_block(&disk, bid), vec![0u8; BLOCK_SIZE]);
+        }
+        let raw_journal_super_block = read_journal_super_block(&disk);
+        assert_eq!(u32::from_be(raw_journal_super_block.start), 0);
+        assert_eq!(u32::from_be(raw_journal_super_block.sequence), SEQUENCE);
+    }
+
+    #[ktest]
+    fn bad_journal_super_block() {
+        let disk = format(true);
+        let offset =
+            JOURNAL_BLOCK * BLOCK_SIZE + core::mem::offset_of!(RawJournalSuperBlock, first);
+        disk.0.write_val(offset, &0u32).unwrap();
+        assert_eq!(Ext2::open(disk.clone()).unwrap_err().error(), Errno::EINVAL);
+    }
+}
@@ -14,6 +14,18 @@
 //!    stored in PageCache, which accelerates the performance of data access.
 //! 3. Compatible with queue-based block device. The filesystem can submits multiple
 //!    BIO requests to be block device at once, thereby enhancing I/O performance.
+//! 4. Compatible with Ext4 images. Extent trees, huge files, 64-bit group descriptors,
+//!    flexible block groups, uninitialized block groups and metadata checksums are
+//!    supported, so the filesystem can also be mounted as `ext4`.
+//! 5. Journaling in the ordered mode. The metadata are committed to the internal JBD2
+//!    journal on sync, after the file data are written back, and the journal is replayed
+//!    on mounting if the filesystem was not unmounted cleanly.
+//! 6. Extended attributes and POSIX ACLs. The xattrs are stored in an xattr block,
+//!    and the ACLs are checked on accessing and inherited on creating files.
+//! 7. Disk quotas. The usage of blocks and inodes is charged to the owners, and the
+//!    quotas are stored in either quota files or the hidden quota inodes of Ext4.
+//! 8. File encryption. The contents and names under a directory with an fscrypt v2
+//!    policy are encrypted with AES-256-XTS and AES-256-CTS using per-file keys.
 //!
 //! # Example
 //!
@@ -35,20 +47,31 @@
 //! Here we summarizes the features that need to be implemented in the future.
 //! 1. Supports merging small read/write operations.
 //! 2. Handles the intermediate failure status correctly.
+//! 3. Forms a journal transaction per operation. Currently a transaction contains all
+//!    the metadata modified since the last sync, and the freed blocks may be reused
+//!    before the transaction is committed. External, fast-commit and async-commit
+//!    journals are not supported.
+//! 4. Maintains hash-indexed directories. The index of a directory is dropped when the
+//!    directory is modified, and the directory is looked up linearly.
+//! 5. Supports the Ext4 features that are rejected on mounting, e.g., inline data, bigalloc
+//!    and more than 2^32 blocks.
 
 pub use fs::Ext2;
 pub use inode::{FilePerm, Inode};
 pub use super_block::MAGIC_NUM;
 
-use crate::fs::ext2::fs::Ext2Type;
+use crate::fs::ext2::fs::{Ext2Type, Ext4Type};
 
 mod block_group;
 mod block_ptr;
+mod checksum;
 mod dir;
+mod extent;
 mod fs;
 mod impl_for_vfs;
 mod indirect_block_cache;
 mod inode;
+mod journal;
 mod prelude;
 mod super_block;
 mod utils;
@@ -56,4 +79,5 @@ mod xattr;
 
 pub(super) fn init() {
     crate::fs::vfs::registry::register(&Ext2Type).unwrap();
+    crate::fs::vfs::registry::register(&Ext4Type).unwrap();
 }
@@ -8,8 +8,8 @@ pub(super) use core::{
 pub(super) use align_ext::AlignExt;
 pub(super) use aster_block::{
     BLOCK_SIZE, BlockDevice, SECTOR_SIZE,
-    bio::{BioDirection, BioSegment, BioStatus, BioWaiter},
-    id::Bid,
+    bio::{BioDirection, BioFlags, BioSegment, BioStatus, BioWaiter},
+    id::{Bid, Sid},
 };
 pub(super) use ostd::{
     mm::{Frame, FrameAllocOptions, Segment, USegment, VmIo},
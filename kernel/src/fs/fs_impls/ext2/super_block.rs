@@ -2,7 +2,7 @@
 
 use ostd::const_assert;
 
-use super::{inode::RawInode, prelude::*};
+use super::{checksum::crc32c, inode::RawInode, prelude::*};
 
 /// The magic number of Ext2.
 pub const MAGIC_NUM: u16 = 0xef53;
@@ -12,6 +12,18 @@ pub const SUPER_BLOCK_OFFSET: usize = 1024;
 
 const SUPER_BLOCK_SIZE: usize = 1024;
 
+/// The size of group descriptors if the 64-bit feature is disabled.
+const DESC_SIZE: usize = 32;
+
+/// The size of group descriptors if the 64-bit feature is enabled.
+const DESC_SIZE_64BIT: usize = 64;
+
+/// The checksum type that represents CRC32C.
+const CHECKSUM_TYPE_CRC32C: u8 = 1;
+
+/// The offset of the checksum in the superblock.
+const CHECKSUM_OFFSET: usize = 0x3fc;
+
 /// The in-memory rust superblock.
 ///
 /// It contains all information about the layout of the Ext2.
@@ -93,29 +105,89 @@ pub struct SuperBlock {
     prealloc_file_blocks: u8,
     /// Number of blocks to preallocate for directories.
     prealloc_dir_blocks: u8,
+    /// Number of blocks reserved for the growth of the group descriptor table.
+    reserved_gdt_blocks: u16,
+    /// Size of group descriptors.
+    desc_size: usize,
+    /// Minimal size of the extra fields in inodes.
+    min_extra_isize: u16,
+    /// Desired size of the extra fields in inodes.
+    want_extra_isize: u16,
+    /// The algorithm to checksum the metadata.
+    checksum_type: u8,
+    /// The seed of the metadata checksums.
+    checksum_seed: u32,
     ///
     /// These fields are reserved and currently serve no purpose.
     ///
     min_rev_level: u16,
     algorithm_usage_bitmap: u32,
-    padding1: u16,
     journal_uuid: [u8; 16],
     journal_ino: u32,
     journal_dev: u32,
     last_orphan: u32,
     hash_seed: [u32; 4],
     def_hash_version: u8,
-    reserved_char_pad: u8,
-    reserved_word_pad: u16,
+    jnl_backup_type: u8,
     default_mount_opts: u32,
     first_meta_bg: u32,
-    reserved: Reserved,
+    mkfs_time: UnixTime,
+    jnl_blocks: [u32; 17],
+    reserved_blocks_count_hi: u32,
+    free_blocks_count_hi: u32,
+    reserved1: [u32; 5],
+    log_groups_per_flex: u8,
+    reserved2: [u8; 2],
+    reserved3: Reserved3,
+    usr_quota_inum: u32,
+    grp_quota_inum: u32,
+    reserved5: [u32; 10],
+    reserved4: Reserved4,
 }
 
 impl TryFrom<RawSuperBlock> for SuperBlock {
     type Error = crate::error::Error;
 
     fn try_from(sb: RawSuperBlock) -> Result<Self> {
+        let feature_compat = FeatureCompatSet::from_bits(sb.feature_compat).ok_or(
+            Error::with_message(Errno::EINVAL, "invalid feature compat set"),
+        )?;
+        let feature_incompat = FeatureInCompatSet::from_bits(sb.feature_incompat).ok_or(
+            Error::with_message(Errno::EINVAL, "invalid feature incompat set"),
+        )?;
+        let feature_ro_compat = FeatureRoCompatSet::from_bits(sb.feature_ro_compat).ok_or(
+            Error::with_message(Errno::EINVAL, "invalid feature ro compat set"),
+        )?;
+        if feature_compat.intersects(FeatureCompatSet::UNSUPPORTED) {
+            return_errno_with_message!(Errno::EINVAL, "unsupported feature compat set");
+        }
+        if feature_incompat.intersects(FeatureInCompatSet::UNSUPPORTED) {
+            return_errno_with_message!(Errno::EINVAL, "unsupported feature incompat set");
+        }
+        if feature_ro_compat.intersects(FeatureRoCompatSet::UNSUPPORTED) {
+            return_errno_with_message!(Errno::EINVAL, "unsupported feature ro compat set");
+        }
+
+        if feature_incompat.contains(FeatureInCompatSet::RECOVER)
+            && !feature_compat.contains(FeatureCompatSet::HAS_JOURNAL)
+        {
+            return_errno_with_message!(Errno::EINVAL, "no journal to recover");
+        }
+
+        let is_64bit = feature_incompat.contains(FeatureInCompatSet::IS_64BIT);
+        if is_64bit && sb.blocks_count_hi != 0 {
+            return_errno_with_message!(Errno::EINVAL, "too many blocks");
+        }
+
+        if feature_ro_compat.contains(FeatureRoCompatSet::METADATA_CSUM) {
+            if sb.checksum_type != CHECKSUM_TYPE_CRC32C {
+                return_errno_with_message!(Errno::EINVAL, "invalid checksum type");
+            }
+            if sb.checksum != sb.compute_checksum() {
+                return_errno_with_message!(Errno::EBADMSG, "bad superblock checksum");
+            }
+        }
+
         Ok(Self {
             inodes_count: sb.inodes_count,
             blocks_count: sb.blocks_count,
@@ -171,34 +243,53 @@ impl TryFrom<RawSuperBlock> for SuperBlock {
                 inode_size
             },
             block_group_idx: sb.block_group_idx as _,
-            feature_compat: FeatureCompatSet::from_bits(sb.feature_compat).ok_or(
-                Error::with_message(Errno::EINVAL, "invalid feature compat set"),
-            )?,
-            feature_incompat: FeatureInCompatSet::from_bits(sb.feature_incompat).ok_or(
-                Error::with_message(Errno::EINVAL, "invalid feature incompat set"),
-            )?,
-            feature_ro_compat: FeatureRoCompatSet::from_bits(sb.feature_ro_compat).ok_or(
-                Error::with_message(Errno::EINVAL, "invalid feature ro compat set"),
-            )?,
+            feature_compat,
+            feature_incompat,
+            feature_ro_compat,
             uuid: sb.uuid,
             volume_name: sb.volume_name,
             last_mounted_dir: sb.last_mounted_dir,
             prealloc_file_blocks: sb.prealloc_file_blocks,
             prealloc_dir_blocks: sb.prealloc_dir_blocks,
+            reserved_gdt_blocks: sb.reserved_gdt_blocks,
+            desc_size: if is_64bit {
+                if sb.desc_size as usize != DESC_SIZE_64BIT {
+                    return_errno_with_message!(
+                        Errno::EINVAL,
+                        "not supported group descriptor size"
+                    );
+                }
+                DESC_SIZE_64BIT
+            } else {
+                DESC_SIZE
+            },
+            min_extra_isize: sb.min_extra_isize,
+            want_extra_isize: sb.want_extra_isize,
+            checksum_type: sb.checksum_type,
+            checksum_seed: sb.checksum_seed,
             min_rev_level: sb.min_rev_level,
             algorithm_usage_bitmap: sb.algorithm_usage_bitmap,
-            padding1: sb.padding1,
             journal_uuid: sb.journal_uuid,
             journal_ino: sb.journal_ino,
             journal_dev: sb.journal_dev,
             last_orphan: sb.last_orphan,
             hash_seed: sb.hash_seed,
             def_hash_version: sb.def_hash_version,
-            reserved_char_pad: sb.reserved_char_pad,
-            reserved_word_pad: sb.reserved_word_pad,
+            jnl_backup_type: sb.jnl_backup_type,
             default_mount_opts: sb.default_mount_opts,
             first_meta_bg: sb.first_meta_bg,
-            reserved: sb.reserved,
+            mkfs_time: sb.mkfs_time,
+            jnl_blocks: sb.jnl_blocks,
+            reserved_blocks_count_hi: sb.reserved_blocks_count_hi,
+            free_blocks_count_hi: sb.free_blocks_count_hi,
+            reserved1: sb.reserved1,
+            log_groups_per_flex: sb.log_groups_per_flex,
+            reserved2: sb.reserved2,
+            reserved3: sb.reserved3,
+            usr_quota_inum: sb.usr_quota_inum,
+            grp_quota_inum: sb.grp_quota_inum,
+            reserved5: sb.reserved5,
+            reserved4: sb.reserved4,
         })
     }
 }
@@ -257,19 +348,16 @@ impl SuperBlock {
     }
 
     /// Returns the compatible feature set.
-    #[expect(dead_code)]
     pub fn feature_compat(&self) -> FeatureCompatSet {
         self.feature_compat
     }
 
     /// Returns the incompatible feature set.
-    #[expect(dead_code)]
     pub fn feature_incompat(&self) -> FeatureInCompatSet {
         self.feature_incompat
     }
 
     /// Returns the readonly-compatible feature set.
-    #[expect(dead_code)]
     pub fn feature_ro_compat(&self) -> FeatureRoCompatSet {
         self.feature_ro_compat
     }
@@ -305,6 +393,113 @@ impl SuperBlock {
         self.free_inodes_count -= 1;
     }
 
+    /// Returns the size of group descriptors.
+    pub fn desc_size(&self) -> usize {
+        self.desc_size
+    }
+
+    /// Returns the number of blocks of the group descriptor table.
+    pub(super) fn gdt_blocks_count(&self) -> usize {
+        (self.block_groups_count() as usize * self.desc_size).div_ceil(self.block_size)
+    }
+
+    /// Returns the number of blocks reserved for the growth of the group descriptor table.
+    pub(super) fn reserved_gdt_blocks(&self) -> usize {
+        if self.feature_compat.contains(FeatureCompatSet::RESIZE_INO) {
+            self.reserved_gdt_blocks as usize
+        } else {
+            0
+        }
+    }
+
+    /// Returns the desired size of the extra fields in new inodes.
+    ///
+    /// The size may exceed the space available in an inode.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.18/source/fs/ext4/super.c>.
+    pub(super) fn want_extra_isize(&self) -> usize {
+        const DEFAULT_EXTRA_ISIZE: u16 = 32;
+
+        if self
+            .feature_ro_compat
+            .contains(FeatureRoCompatSet::EXTRA_ISIZE)
+        {
+            DEFAULT_EXTRA_ISIZE
+                .max(self.want_extra_isize)
+                .max(self.min_extra_isize) as usize
+        } else {
+            DEFAULT_EXTRA_ISIZE as usize
+        }
+    }
+
+    /// Returns the algorithm to checksum the metadata.
+    pub(super) fn metadata_checksum(&self) -> MetadataChecksum {
+        if self
+            .feature_ro_compat
+            .contains(FeatureRoCompatSet::METADATA_CSUM)
+        {
+            let seed = if self
+                .feature_incompat
+                .contains(FeatureInCompatSet::CSUM_SEED)
+            {
+                self.checksum_seed
+            } else {
+                crc32c(!0, &self.uuid)
+            };
+            MetadataChecksum::Crc32c { seed }
+        } else if self
+            .feature_ro_compat
+            .contains(FeatureRoCompatSet::GDT_CSUM)
+        {
+            MetadataChecksum::Crc16 { uuid: self.uuid }
+        } else {
+            MetadataChecksum::None
+        }
+    }
+
+    /// Checks if the filesystem has a journal.
+    pub(super) fn has_journal(&self) -> bool {
+        self.feature_compat.contains(FeatureCompatSet::HAS_JOURNAL)
+    }
+
+    /// Returns the inode number of the journal.
+    pub(super) fn journal_ino(&self) -> u32 {
+        self.journal_ino
+    }
+
+    /// Returns the inode numbers of the user and group quota files.
+    ///
+    /// The quota files are hidden inodes if the quota feature is enabled.
+    /// An inode number of zero means that the quota file does not exist.
+    pub(super) fn quota_inos(&self) -> [u32; 2] {
+        if !self.feature_ro_compat.contains(FeatureRoCompatSet::QUOTA) {
+            return [0; 2];
+        }
+        [self.usr_quota_inum, self.grp_quota_inum]
+    }
+
+    /// Checks if the journal should be replayed when the filesystem is mounted.
+    pub(super) fn needs_recovery(&self) -> bool {
+        self.feature_incompat.contains(FeatureInCompatSet::RECOVER)
+    }
+
+    /// Marks that the journal should be replayed
+    /// if the filesystem is not unmounted cleanly.
+    pub(super) fn set_needs_recovery(&mut self) {
+        self.feature_incompat.insert(FeatureInCompatSet::RECOVER);
+    }
+
+    /// Marks that encrypted inodes are present.
+    pub(super) fn set_encrypt(&mut self) {
+        self.feature_incompat.insert(FeatureInCompatSet::ENCRYPT);
+    }
+
+    /// Checks if the block group contains a copy of the super block
+    /// and the group descriptor table.
+    pub(super) fn has_super(&self, block_group_idx: usize) -> bool {
+        block_group_idx == 0 || self.is_backup_group(block_group_idx)
+    }
+
     /// Checks if the block group will backup the super block.
     pub(super) fn is_backup_group(&self, block_group_idx: usize) -> bool {
         if block_group_idx == 0 {
@@ -354,6 +549,17 @@ impl SuperBlock {
     }
 }
 
+/// The algorithm to checksum the metadata.
+#[derive(Clone, Copy, Debug)]
+pub(super) enum MetadataChecksum {
+    /// The metadata have no checksums.
+    None,
+    /// Only the group descriptors have CRC16 checksums.
+    Crc16 { uuid: [u8; 16] },
+    /// The metadata have CRC32C checksums.
+    Crc32c { seed: u32 },
+}
+
 bitflags! {
     /// Compatible feature set.
     pub struct FeatureCompatSet: u32 {
@@ -369,9 +575,28 @@ bitflags! {
         const RESIZE_INO = 1 << 4;
         /// Directories use hash index
         const DIR_INDEX = 1 << 5;
+        /// Block groups are lazily initialized
+        const LAZY_BG = 1 << 6;
+        /// Exclude inode (not used)
+        const EXCLUDE_INODE = 1 << 7;
+        /// Exclude bitmap (not used)
+        const EXCLUDE_BITMAP = 1 << 8;
+        /// Backup superblocks are only in the specified block groups
+        const SPARSE_SUPER2 = 1 << 9;
+        /// Fast commits are supported by the journal
+        const FAST_COMMIT = 1 << 10;
+        /// Inode numbers are never changed
+        const STABLE_INODES = 1 << 11;
+        /// Orphan inodes are tracked by an orphan file
+        const ORPHAN_FILE = 1 << 12;
     }
 }
 
+impl FeatureCompatSet {
+    /// The features that are not supported.
+    const UNSUPPORTED: Self = Self::SPARSE_SUPER2;
+}
+
 bitflags! {
     /// Incompatible feature set.
     pub struct FeatureInCompatSet: u32 {
@@ -385,9 +610,45 @@ bitflags! {
         const JOURNAL_DEV = 1 << 3;
         /// Metablock block group
         const META_BG = 1 << 4;
+        /// Files use extent trees
+        const EXTENTS = 1 << 6;
+        /// File system can have more than 2^32 blocks
+        const IS_64BIT = 1 << 7;
+        /// Multiple mount protection
+        const MMP = 1 << 8;
+        /// Flexible block groups
+        const FLEX_BG = 1 << 9;
+        /// Inodes can be used to store large extended attribute values
+        const EA_INODE = 1 << 10;
+        /// Data in directory entries
+        const DIRDATA = 1 << 12;
+        /// The checksum seed is stored in the superblock
+        const CSUM_SEED = 1 << 13;
+        /// Directories can be larger than 2GB or have 3-level hash trees
+        const LARGEDIR = 1 << 14;
+        /// Data in inodes
+        const INLINE_DATA = 1 << 15;
+        /// Encrypted inodes are present
+        const ENCRYPT = 1 << 16;
+        /// Directories can be case-insensitive
+        const CASEFOLD = 1 << 17;
     }
 }
 
+impl FeatureInCompatSet {
+    /// The features that are not supported.
+    const UNSUPPORTED: Self = Self::from_bits_truncate(
+        Self::COMPRESSION.bits
+            | Self::JOURNAL_DEV.bits
+            | Self::MMP.bits
+            | Self::EA_INODE.bits
+            | Self::DIRDATA.bits
+            | Self::LARGEDIR.bits
+            | Self::INLINE_DATA.bits
+            | Self::CASEFOLD.bits,
+    );
+}
+
 bitflags! {
     /// Readonly-compatible feature set.
     pub struct FeatureRoCompatSet: u32 {
@@ -397,9 +658,51 @@ bitflags! {
         const LARGE_FILE = 1 << 1;
         /// Directory contents are stored in the form of a Binary Tree
         const BTREE_DIR = 1 << 2;
+        /// Files can be larger than 2TB
+        const HUGE_FILE = 1 << 3;
+        /// Group descriptors have checksums
+        const GDT_CSUM = 1 << 4;
+        /// Directories can have more than 65000 subdirectories
+        const DIR_NLINK = 1 << 5;
+        /// Inodes can have extra fields
+        const EXTRA_ISIZE = 1 << 6;
+        /// File system has a snapshot
+        const HAS_SNAPSHOT = 1 << 7;
+        /// Quota is tracked
+        const QUOTA = 1 << 8;
+        /// File system allocates blocks in clusters
+        const BIGALLOC = 1 << 9;
+        /// Metadata have checksums
+        const METADATA_CSUM = 1 << 10;
+        /// Replicas are supported
+        const REPLICA = 1 << 11;
+        /// File system can only be mounted read-only
+        const READONLY = 1 << 12;
+        /// Project quota is tracked
+        const PROJECT = 1 << 13;
+        /// Blocks can be shared by files
+        const SHARED_BLOCKS = 1 << 14;
+        /// Verity inodes are present
+        const VERITY = 1 << 15;
+        /// The orphan file may be non-empty
+        const ORPHAN_PRESENT = 1 << 16;
     }
 }
 
+impl FeatureRoCompatSet {
+    /// The features that are not supported.
+    const UNSUPPORTED: Self = Self::from_bits_truncate(
+        Self::HAS_SNAPSHOT.bits
+            | Self::BIGALLOC.bits
+            | Self::REPLICA.bits
+            | Self::READONLY.bits
+            | Self::PROJECT.bits
+            | Self::SHARED_BLOCKS.bits
+            | Self::VERITY.bits
+            | Self::ORPHAN_PRESENT.bits,
+    );
+}
+
 bitflags! {
     /// Filesystem state.
     ///
@@ -409,6 +712,8 @@ bitflags! {
         const VALID = 1 << 0;
         /// Errors detected
         const ERROR = 1 << 1;
+        /// Orphans being recovered
+        const ORPHANS = 1 << 2;
     }
 }
 
@@ -495,7 +800,8 @@ pub(super) struct RawSuperBlock {
     pub algorithm_usage_bitmap: u32,
     pub prealloc_file_blocks: u8,
     pub prealloc_dir_blocks: u8,
-    padding1: u16,
+    /// Number of blocks reserved for the growth of the group descriptor table.
+    pub reserved_gdt_blocks: u16,
     ///
     /// These fields are for journaling support in Ext3.
     ///
@@ -511,18 +817,64 @@ pub(super) struct RawSuperBlock {
     pub hash_seed: [u32; 4],
     /// Default hash version to use
     pub def_hash_version: u8,
-    reserved_char_pad: u8,
-    reserved_word_pad: u16,
+    /// Type of the backup of the journal inode.
+    pub jnl_backup_type: u8,
+    /// Size of group descriptors.
+    pub desc_size: u16,
     /// Default mount options.
     pub default_mount_opts: u32,
     /// First metablock block group.
     pub first_meta_bg: u32,
-    reserved: Reserved,
+    ///
+    /// These fields are for Ext4.
+    ///
+    /// Time when the file system was created.
+    pub mkfs_time: UnixTime,
+    /// Backup of the journal inode.
+    pub jnl_blocks: [u32; 17],
+    pub blocks_count_hi: u32,
+    pub reserved_blocks_count_hi: u32,
+    pub free_blocks_count_hi: u32,
+    /// Minimal size of the extra fields in inodes.
+    pub min_extra_isize: u16,
+    /// Desired size of the extra fields in inodes.
+    pub want_extra_isize: u16,
+    reserved1: [u32; 5],
+    /// The number to left-shift 1 to obtain the number of groups in a flexible group.
+    pub log_groups_per_flex: u8,
+    /// The algorithm to checksum the metadata.
+    pub checksum_type: u8,
+    reserved2: [u8; 2],
+    reserved3: Reserved3,
+    /// Inode number of the user quota file.
+    pub usr_quota_inum: u32,
+    /// Inode number of the group quota file.
+    pub grp_quota_inum: u32,
+    reserved5: [u32; 10],
+    /// The seed of the metadata checksums if `FeatureInCompatSet::CSUM_SEED` is set.
+    pub checksum_seed: u32,
+    reserved4: Reserved4,
+    /// Checksum of the superblock.
+    pub checksum: u32,
+}
+
+impl RawSuperBlock {
+    /// Computes the checksum of the superblock.
+    fn compute_checksum(&self) -> u32 {
+        crc32c(!0, &self.as_bytes()[..CHECKSUM_OFFSET])
+    }
+
+    /// Updates the checksum of the superblock if metadata checksums are enabled.
+    pub(super) fn update_checksum(&mut self) {
+        if self.feature_ro_compat & FeatureRoCompatSet::METADATA_CSUM.bits() != 0 {
+            self.checksum = self.compute_checksum();
+        }
+    }
 }
 
 impl From<&SuperBlock> for RawSuperBlock {
     fn from(sb: &SuperBlock) -> Self {
-        Self {
+        let mut raw_sb = Self {
             inodes_count: sb.inodes_count,
             blocks_count: sb.blocks_count,
             reserved_blocks_count: sb.reserved_blocks_count,
@@ -560,19 +912,54 @@ impl From<&SuperBlock> for RawSuperBlock {
             algorithm_usage_bitmap: sb.algorithm_usage_bitmap,
             prealloc_file_blocks: sb.prealloc_file_blocks,
             prealloc_dir_blocks: sb.prealloc_dir_blocks,
-            padding1: sb.padding1,
+            reserved_gdt_blocks: sb.reserved_gdt_blocks,
             journal_uuid: sb.journal_uuid,
             journal_ino: sb.journal_ino,
             journal_dev: sb.journal_dev,
             last_orphan: sb.last_orphan,
             hash_seed: sb.hash_seed,
             def_hash_version: sb.def_hash_version,
-            reserved_char_pad: sb.reserved_char_pad,
-            reserved_word_pad: sb.reserved_word_pad,
+            jnl_backup_type: sb.jnl_backup_type,
+            desc_size: if sb.feature_incompat.contains(FeatureInCompatSet::IS_64BIT) {
+                sb.desc_size as u16
+            } else {
+                0
+            },
             default_mount_opts: sb.default_mount_opts,
             first_meta_bg: sb.first_meta_bg,
-            reserved: sb.reserved,
-        }
+            mkfs_time: sb.mkfs_time,
+            jnl_blocks: sb.jnl_blocks,
+            blocks_count_hi: 0,
+            reserved_blocks_count_hi: sb.reserved_blocks_count_hi,
+            free_blocks_count_hi: sb.free_blocks_count_hi,
+            min_extra_isize: sb.min_extra_isize,
+            want_extra_isize: sb.want_extra_isize,
+            reserved1: sb.reserved1,
+            log_groups_per_flex: sb.log_groups_per_flex,
+            checksum_type: sb.checksum_type,
+            reserved2: sb.reserved2,
+            reserved3: sb.reserved3,
+            usr_quota_inum: sb.usr_quota_inum,
+            grp_quota_inum: sb.grp_quota_inum,
+            reserved5: sb.reserved5,
+            checksum_seed: sb.checksum_seed,
+            reserved4: sb.reserved4,
+            checksum: 0,
+        };
+        raw_sb.update_checksum();
+        raw_sb
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+// FIXME: `pub(super)` is needed due to a bug in `zerocopy`. See
+// <https://github.com/google/zerocopy/issues/1292>.
+pub(super) struct Reserved3([u32; 50]);
+
+impl Default for Reserved3 {
+    fn default() -> Self {
+        Self([0u32; 50])
     }
 }
 
@@ -580,10 +967,10 @@ impl From<&SuperBlock> for RawSuperBlock {
 #[derive(Clone, Copy, Debug, Pod)]
 // FIXME: `pub(super)` is needed due to a bug in `zerocopy`. See
 // <https://github.com/google/zerocopy/issues/1292>.
-pub(super) struct Reserved([u32; 190]);
+pub(super) struct Reserved4([u32; 98]);
 
-impl Default for Reserved {
+impl Default for Reserved4 {
     fn default() -> Self {
-        Self([0u32; 190])
+        Self([0u32; 98])
     }
 }
@@ -1,12 +1,21 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use core::mem::offset_of;
+
 use ostd::mm::{HasSize, io::util::HasVmReaderWriter};
 
-use super::{Ext2, Inode, block_ptr::Ext2Bid, prelude::*};
+use super::{Ext2, Inode, block_ptr::Ext2Bid, checksum::crc32c, prelude::*};
 use crate::fs::vfs::xattr::{XATTR_NAME_MAX_LEN, XattrName, XattrNamespace, XattrSetFlags};
 
 const EXT2_XATTR_MAGIC: u32 = 0xEA020000;
 
+/// The name index of the encryption context, which is hidden from the users.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/fs/ext4/xattr.h#L41>
+const EXT4_XATTR_INDEX_ENCRYPTION: u8 = 9;
+/// The name of the encryption context.
+const EXT4_XATTR_NAME_ENCRYPTION_CONTEXT: &[u8] = b"c";
+
 /// The xattr header of an ext2 inode, organized
 /// at the beginning of an xattr block.
 #[repr(C)]
@@ -16,7 +25,8 @@ struct XattrHeader {
     ref_count: u32,
     nblocks: u32,
     hash: u32,
-    reserved: [u32; 4],
+    checksum: u32,
+    reserved: [u32; 3],
 }
 
 const XATTR_HEADER_SIZE: usize = size_of::<XattrHeader>();
@@ -132,15 +142,17 @@ impl Xattr {
             self.inode().set_acl(new_bid);
         // Need to load the xattr block from device
         } else if cache.header.is_none() {
-            fs.block_device().read_blocks(
-                cache.bid,
-                BioSegment::new_from_segment(self.blocks_buf.clone(), BioDirection::FromDevice),
-            )?;
+            fs.read_metadata_blocks(cache.bid.to_raw() as Ext2Bid, self.blocks_buf.clone())?;
 
             let header = self.blocks_buf.read_val::<XattrHeader>(0)?;
             if header.magic != EXT2_XATTR_MAGIC {
                 return_errno_with_message!(Errno::EINVAL, "invalid xattr magic");
             }
+            if let Some(seed) = fs.csum_seed() {
+                if header.checksum != self.compute_checksum(cache.bid, seed)? {
+                    return_errno_with_message!(Errno::EBADMSG, "bad xattr block checksum");
+                }
+            }
 
             let mut cache = cache.upgrade();
             cache.header = Some(header);
@@ -154,14 +166,29 @@ impl Xattr {
         name: XattrName,
         value_reader: &mut VmReader,
         flags: XattrSetFlags,
+    ) -> Result<()> {
+        self.set_entry(
+            name.namespace() as u8,
+            name.full_name().as_bytes(),
+            value_reader,
+            flags,
+        )
+    }
+
+    /// Sets the value of the entry with the name index and the name.
+    fn set_entry(
+        &self,
+        name_index: u8,
+        name: &[u8],
+        value_reader: &mut VmReader,
+        flags: XattrSetFlags,
     ) -> Result<()> {
         self.lazy_init()?;
 
-        let namespace = name.namespace();
-        let name_len = name.full_name_len();
+        let name_len = name.len();
         let value_len = value_reader.remain();
         let cache = self.cache.upread();
-        if let Some((offset, mut entry)) = cache.find_entry(&name, &self.blocks_buf) {
+        if let Some((offset, mut entry)) = cache.find_entry(name_index, name, &self.blocks_buf) {
             if flags.contains(XattrSetFlags::CREATE_ONLY) {
                 return_errno_with_message!(Errno::EEXIST, "the target xattr already exists");
             }
@@ -206,7 +233,7 @@ impl Xattr {
                 .ok_or(Error::new(Errno::ENOSPC))?;
             let new_entry = XattrEntry {
                 name_len: name_len as _,
-                name_index: namespace as _,
+                name_index,
                 value_offset: new_value_offset as _,
                 value_block: 0, // TBD
                 value_len: value_len as _,
@@ -214,10 +241,8 @@ impl Xattr {
             };
 
             self.blocks_buf.write_val(new_entry_offset, &new_entry)?;
-            self.blocks_buf.write_bytes(
-                new_entry_offset + XATTR_ENTRY_SIZE,
-                name.full_name().as_bytes(),
-            )?;
+            self.blocks_buf
+                .write_bytes(new_entry_offset + XATTR_ENTRY_SIZE, name)?;
             self.blocks_buf.write(new_value_offset, value_reader)?;
 
             let mut cache = cache.upgrade();
@@ -229,13 +254,20 @@ impl Xattr {
     }
 
     pub fn get(&self, name: XattrName, value_writer: &mut VmWriter) -> Result<usize> {
+        if !self.has_block() {
+            return_errno_with_message!(Errno::ENODATA, "the target xattr does not exist");
+        }
         self.lazy_init()?;
 
         let value_avail_len = value_writer.avail();
         let (_, entry) = self
             .cache
             .read()
-            .find_entry(&name, &self.blocks_buf)
+            .find_entry(
+                name.namespace() as u8,
+                name.full_name().as_bytes(),
+                &self.blocks_buf,
+            )
             .ok_or(Error::new(Errno::ENODATA))?;
 
         let value_len = entry.value_len as usize;
@@ -257,7 +289,54 @@ impl Xattr {
         Ok(value_len)
     }
 
+    /// Gets the value of the xattr named `name`, or `None` if the xattr does not exist.
+    pub fn get_bytes(&self, name: XattrName) -> Result<Option<Vec<u8>>> {
+        self.get_entry_bytes(name.namespace() as u8, name.full_name().as_bytes())
+    }
+
+    /// Gets the encryption context, or `None` if the inode is not encrypted.
+    pub fn encryption_context(&self) -> Result<Option<Vec<u8>>> {
+        self.get_entry_bytes(
+            EXT4_XATTR_INDEX_ENCRYPTION,
+            EXT4_XATTR_NAME_ENCRYPTION_CONTEXT,
+        )
+    }
+
+    /// Sets the encryption context.
+    pub fn set_encryption_context(&self, context: &[u8]) -> Result<()> {
+        self.set_entry(
+            EXT4_XATTR_INDEX_ENCRYPTION,
+            EXT4_XATTR_NAME_ENCRYPTION_CONTEXT,
+            &mut VmReader::from(context).to_fallible(),
+            XattrSetFlags::CREATE_ONLY,
+        )
+    }
+
+    /// Gets the value of the entry with the name index and the name, or `None` if the
+    /// entry does not exist.
+    fn get_entry_bytes(&self, name_index: u8, name: &[u8]) -> Result<Option<Vec<u8>>> {
+        if !self.has_block() {
+            return Ok(None);
+        }
+        self.lazy_init()?;
+
+        let Some((_, entry)) = self
+            .cache
+            .read()
+            .find_entry(name_index, name, &self.blocks_buf)
+        else {
+            return Ok(None);
+        };
+        let mut value = vec![0u8; entry.value_len as usize];
+        self.blocks_buf
+            .read_bytes(entry.value_offset as usize, &mut value)?;
+        Ok(Some(value))
+    }
+
     pub fn list(&self, namespace: XattrNamespace, list_writer: &mut VmWriter) -> Result<usize> {
+        if !self.has_block() {
+            return Ok(0);
+        }
         self.lazy_init()?;
 
         let list_avail_len = list_writer.avail();
@@ -267,7 +346,15 @@ impl Xattr {
             .entries
             .iter()
             .filter_map(|(offset, entry)| {
-                if namespace.is_user() && entry.name_index != XattrNamespace::User as u8 {
+                // The encryption context is never visible.
+                if XattrNamespace::try_from(entry.name_index).is_err() {
+                    return None;
+                }
+                // The system xattrs (i.e., POSIX ACLs) are visible to all users.
+                if namespace.is_user()
+                    && entry.name_index != XattrNamespace::User as u8
+                    && entry.name_index != XattrNamespace::System as u8
+                {
                     None
                 } else {
                     Some((offset, entry.name_len as usize))
@@ -300,16 +387,22 @@ impl Xattr {
     }
 
     pub fn remove(&self, name: XattrName) -> Result<()> {
+        if !self.has_block() {
+            return_errno_with_message!(Errno::ENODATA, "the target xattr does not exist");
+        }
         self.lazy_init()?;
 
         let cache = self.cache.upread();
-        let (offset, entry) =
-            cache
-                .find_entry(&name, &self.blocks_buf)
-                .ok_or(Error::with_message(
-                    Errno::ENODATA,
-                    "the target xattr does not exist",
-                ))?;
+        let (offset, entry) = cache
+            .find_entry(
+                name.namespace() as u8,
+                name.full_name().as_bytes(),
+                &self.blocks_buf,
+            )
+            .ok_or(Error::with_message(
+                Errno::ENODATA,
+                "the target xattr does not exist",
+            ))?;
 
         let len = entry.total_len();
         self.blocks_buf
@@ -328,10 +421,17 @@ impl Xattr {
     pub fn flush(&self) -> Result<()> {
         let cache = self.cache.upread();
         if cache.is_dirty() {
-            self.fs().block_device().write_blocks(
-                cache.bid,
-                BioSegment::new_from_segment(self.blocks_buf.clone(), BioDirection::ToDevice),
-            )?;
+            if let Some(seed) = self.fs().csum_seed() {
+                let checksum = self.compute_checksum(cache.bid, seed)?;
+                self.blocks_buf
+                    .write_val(offset_of!(XattrHeader, checksum), &checksum)?;
+            }
+            let bio_waiter = self
+                .fs()
+                .write_metadata_blocks_async(cache.bid.to_raw() as Ext2Bid, &self.blocks_buf)?;
+            if Some(BioStatus::Complete) != bio_waiter.wait() {
+                return_errno_with_message!(Errno::EIO, "failed to write the xattr block");
+            }
             cache.upgrade().clear_dirty();
         }
         Ok(())
@@ -348,6 +448,24 @@ impl Xattr {
         Ok(())
     }
 
+    /// Returns whether the xattr block has been allocated.
+    ///
+    /// If not, there are no xattrs and the block is allocated on the first `set`.
+    fn has_block(&self) -> bool {
+        self.cache.read().bid.to_raw() != 0
+    }
+
+    /// Computes the checksum of the xattr block at `bid`.
+    fn compute_checksum(&self, bid: Bid, seed: u32) -> Result<u32> {
+        let mut block = vec![0u8; self.blocks_buf.size()];
+        self.blocks_buf.read_bytes(0, &mut block)?;
+        let checksum_offset = offset_of!(XattrHeader, checksum);
+        block[checksum_offset..checksum_offset + size_of::<u32>()].fill(0);
+
+        let checksum = crc32c(seed, &bid.to_raw().to_le_bytes());
+        Ok(crc32c(checksum, &block))
+    }
+
     fn fs(&self) -> Arc<Ext2> {
         self.fs.upgrade().unwrap()
     }
@@ -374,7 +492,8 @@ impl XattrCache {
         while offset < self.capacity_bytes - XATTR_ENTRY_VALUE_GAP - XATTR_ENTRY_SIZE {
             let entry = blocks_buf.read_val::<XattrEntry>(offset)?;
             if entry.name_len == 0
-                || XattrNamespace::try_from(entry.name_index).is_err()
+                || (XattrNamespace::try_from(entry.name_index).is_err()
+                    && entry.name_index != EXT4_XATTR_INDEX_ENCRYPTION)
                 || entry.value_offset as usize + entry.value_len as usize > self.capacity_bytes
             {
                 offset += XATTR_ALIGN;
@@ -390,17 +509,20 @@ impl XattrCache {
         Ok(())
     }
 
-    pub fn find_entry(&self, name: &XattrName, buf: &USegment) -> Option<(usize, XattrEntry)> {
-        let namespace = name.namespace();
-        let name_bytes = name.full_name().as_bytes();
-        let name_len = name_bytes.len();
+    pub fn find_entry(
+        &self,
+        name_index: u8,
+        name: &[u8],
+        buf: &USegment,
+    ) -> Option<(usize, XattrEntry)> {
+        let name_len = name.len();
         debug_assert!(name_len > 0);
         let mut name_buf = [0u8; XATTR_NAME_MAX_LEN];
         for (offset, entry) in &self.entries {
-            if entry.name_index == namespace as u8 && entry.name_len == name_len as u8 {
+            if entry.name_index == name_index && entry.name_len == name_len as u8 {
                 buf.read_bytes(offset + XATTR_ENTRY_SIZE, &mut name_buf[..name_len])
                     .unwrap();
-                if &name_buf[..name_len] == name_bytes {
+                if &name_buf[..name_len] == name {
                     return Some((*offset, *entry));
                 }
             }
@@ -482,6 +604,7 @@ impl Default for XattrHeader {
             nblocks: XATTR_NBLOCKS as _,
             ref_count: Default::default(),
             hash: Default::default(),
+            checksum: Default::default(),
             reserved: Default::default(),
         }
     }
@@ -0,0 +1,375 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The messages of the FUSE protocol.
+//!
+//! Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/fuse.h>.
+
+use crate::prelude::*;
+
+/// The major version of the protocol.
+pub(super) const KERNEL_VERSION: u32 = 7;
+/// The minor version of the protocol.
+///
+/// The messages are those of 7.31, which are understood by all the maintained versions
+/// of libfuse.
+pub(super) const KERNEL_MINOR_VERSION: u32 = 31;
+
+/// The node ID of the root directory.
+pub(super) const ROOT_ID: u64 = 1;
+
+/// The minimum size of the buffers that the daemon reads the requests into.
+pub(super) const MIN_READ_BUFFER: usize = 8192;
+
+/// The operation codes of the requests.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Opcode {
+    Lookup = 1,
+    Forget = 2,
+    Getattr = 3,
+    Setattr = 4,
+    Readlink = 5,
+    Symlink = 6,
+    Mknod = 8,
+    Mkdir = 9,
+    Unlink = 10,
+    Rmdir = 11,
+    Rename = 12,
+    Link = 13,
+    Open = 14,
+    Read = 15,
+    Write = 16,
+    Statfs = 17,
+    Release = 18,
+    Fsync = 20,
+    Flush = 25,
+    Init = 26,
+    Opendir = 27,
+    Readdir = 28,
+    Releasedir = 29,
+    Interrupt = 36,
+    Destroy = 38,
+    Fallocate = 43,
+}
+
+impl Opcode {
+    /// Returns whether the daemon replies to the requests of this operation.
+    pub(super) fn has_reply(self) -> bool {
+        !matches!(self, Self::Forget | Self::Interrupt)
+    }
+}
+
+bitflags! {
+    /// The flags of the `INIT` request and reply.
+    pub(super) struct InitFlags: u32 {
+        const ASYNC_READ = 1 << 0;
+        const POSIX_LOCKS = 1 << 1;
+        const ATOMIC_O_TRUNC = 1 << 3;
+        const BIG_WRITES = 1 << 5;
+        const DONT_MASK = 1 << 6;
+        const AUTO_INVAL_DATA = 1 << 12;
+        const DO_READDIRPLUS = 1 << 13;
+        const MAX_PAGES = 1 << 22;
+    }
+}
+
+bitflags! {
+    /// The fields that are valid in a `SETATTR` request.
+    pub(super) struct SetattrValid: u32 {
+        const MODE = 1 << 0;
+        const UID = 1 << 1;
+        const GID = 1 << 2;
+        const SIZE = 1 << 3;
+        const ATIME = 1 << 4;
+        const MTIME = 1 << 5;
+        const FH = 1 << 6;
+        const CTIME = 1 << 10;
+    }
+}
+
+bitflags! {
+    /// The flags of the `OPEN` reply.
+    pub(super) struct OpenOutFlags: u32 {
+        /// Bypasses the page cache for this file.
+        const DIRECT_IO = 1 << 0;
+        const KEEP_CACHE = 1 << 1;
+        const NONSEEKABLE = 1 << 2;
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct InHeader {
+    pub(super) len: u32,
+    pub(super) opcode: u32,
+    pub(super) unique: u64,
+    pub(super) nodeid: u64,
+    pub(super) uid: u32,
+    pub(super) gid: u32,
+    pub(super) pid: u32,
+    pub(super) total_extlen: u16,
+    pub(super) padding: u16,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct OutHeader {
+    pub(super) len: u32,
+    /// The negated error number, or zero on success.
+    pub(super) error: i32,
+    pub(super) unique: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct InitIn {
+    pub(super) major: u32,
+    pub(super) minor: u32,
+    pub(super) max_readahead: u32,
+    pub(super) flags: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct InitOut {
+    pub(super) major: u32,
+    pub(super) minor: u32,
+    pub(super) max_readahead: u32,
+    pub(super) flags: u32,
+    pub(super) max_background: u16,
+    pub(super) congestion_threshold: u16,
+    pub(super) max_write: u32,
+    pub(super) time_gran: u32,
+    pub(super) max_pages: u16,
+    pub(super) map_alignment: u16,
+    pub(super) flags2: u32,
+    pub(super) unused: [u32; 7],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct Attr {
+    pub(super) ino: u64,
+    pub(super) size: u64,
+    pub(super) blocks: u64,
+    pub(super) atime: u64,
+    pub(super) mtime: u64,
+    pub(super) ctime: u64,
+    pub(super) atimensec: u32,
+    pub(super) mtimensec: u32,
+    pub(super) ctimensec: u32,
+    pub(super) mode: u32,
+    pub(super) nlink: u32,
+    pub(super) uid: u32,
+    pub(super) gid: u32,
+    pub(super) rdev: u32,
+    pub(super) blksize: u32,
+    pub(super) flags: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct EntryOut {
+    pub(super) nodeid: u64,
+    pub(super) generation: u64,
+    pub(super) entry_valid: u64,
+    pub(super) attr_valid: u64,
+    pub(super) entry_valid_nsec: u32,
+    pub(super) attr_valid_nsec: u32,
+    pub(super) attr: Attr,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct ForgetIn {
+    pub(super) nlookup: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct GetattrIn {
+    pub(super) getattr_flags: u32,
+    pub(super) dummy: u32,
+    pub(super) fh: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct AttrOut {
+    pub(super) attr_valid: u64,
+    pub(super) attr_valid_nsec: u32,
+    pub(super) dummy: u32,
+    pub(super) attr: Attr,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct SetattrIn {
+    pub(super) valid: u32,
+    pub(super) padding: u32,
+    pub(super) fh: u64,
+    pub(super) size: u64,
+    pub(super) lock_owner: u64,
+    pub(super) atime: u64,
+    pub(super) mtime: u64,
+    pub(super) ctime: u64,
+    pub(super) atimensec: u32,
+    pub(super) mtimensec: u32,
+    pub(super) ctimensec: u32,
+    pub(super) mode: u32,
+    pub(super) unused4: u32,
+    pub(super) uid: u32,
+    pub(super) gid: u32,
+    pub(super) unused5: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct MknodIn {
+    pub(super) mode: u32,
+    pub(super) rdev: u32,
+    pub(super) umask: u32,
+    pub(super) padding: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct MkdirIn {
+    pub(super) mode: u32,
+    pub(super) umask: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct RenameIn {
+    pub(super) newdir: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct LinkIn {
+    pub(super) oldnodeid: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct OpenIn {
+    pub(super) flags: u32,
+    pub(super) open_flags: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct OpenOut {
+    pub(super) fh: u64,
+    pub(super) open_flags: u32,
+    pub(super) padding: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct ReleaseIn {
+    pub(super) fh: u64,
+    pub(super) flags: u32,
+    pub(super) release_flags: u32,
+    pub(super) lock_owner: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct FlushIn {
+    pub(super) fh: u64,
+    pub(super) unused: u32,
+    pub(super) padding: u32,
+    pub(super) lock_owner: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct ReadIn {
+    pub(super) fh: u64,
+    pub(super) offset: u64,
+    pub(super) size: u32,
+    pub(super) read_flags: u32,
+    pub(super) lock_owner: u64,
+    pub(super) flags: u32,
+    pub(super) padding: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct WriteIn {
+    pub(super) fh: u64,
+    pub(super) offset: u64,
+    pub(super) size: u32,
+    pub(super) write_flags: u32,
+    pub(super) lock_owner: u64,
+    pub(super) flags: u32,
+    pub(super) padding: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct WriteOut {
+    pub(super) size: u32,
+    pub(super) padding: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct FsyncIn {
+    pub(super) fh: u64,
+    pub(super) fsync_flags: u32,
+    pub(super) padding: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct FallocateIn {
+    pub(super) fh: u64,
+    pub(super) offset: u64,
+    pub(super) length: u64,
+    pub(super) mode: u32,
+    pub(super) padding: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct InterruptIn {
+    pub(super) unique: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct StatfsOut {
+    pub(super) blocks: u64,
+    pub(super) bfree: u64,
+    pub(super) bavail: u64,
+    pub(super) files: u64,
+    pub(super) ffree: u64,
+    pub(super) bsize: u32,
+    pub(super) namelen: u32,
+    pub(super) frsize: u32,
+    pub(super) padding: u32,
+    pub(super) spare: [u32; 6],
+}
+
+/// The header of an entry in the reply of `READDIR`, which is followed by the name
+/// and padded to 8 bytes.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct Dirent {
+    pub(super) ino: u64,
+    /// The offset of the next entry.
+    pub(super) off: u64,
+    pub(super) namelen: u32,
+    pub(super) type_: u32,
+}
+
+/// Parses the body of a reply, which must be no shorter than the message.
+pub(super) fn parse_reply<T: Pod>(body: &[u8]) -> Result<T> {
+    if body.len() < size_of::<T>() {
+        return_errno_with_message!(Errno::EIO, "the FUSE reply is too short");
+    }
+    Ok(T::from_first_bytes(body))
+}
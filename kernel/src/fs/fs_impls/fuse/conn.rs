@@ -0,0 +1,537 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use aster_virtio::device::filesystem::device::FileSystemDevice;
+use ostd::{sync::WaitQueue, task::Task};
+
+use super::abi::{
+    InHeader, InitFlags, InitIn, InitOut, InterruptIn, KERNEL_MINOR_VERSION, KERNEL_VERSION,
+    MIN_READ_BUFFER, Opcode, OutHeader,
+};
+use crate::{
+    events::IoEvents,
+    prelude::*,
+    process::{posix_thread::AsPosixThread, signal::Pollee},
+};
+
+/// The maximum number of bytes that the daemon may write in a `WRITE` request,
+/// if it does not specify a larger one.
+const DEFAULT_MAX_WRITE: usize = 4096;
+/// The upper limit of the bytes of a `WRITE` request.
+const MAX_MAX_WRITE: usize = 32 * PAGE_SIZE;
+/// The maximum number of bytes to read ahead, which is advertised to the daemon.
+const MAX_READAHEAD: u32 = 32 * PAGE_SIZE as u32;
+
+/// The maximum number of bytes in the body of a reply from a virtio filesystem device,
+/// which is no less than the largest `READ` request.
+const MAX_VIRTIO_REPLY: usize = 32 * PAGE_SIZE;
+
+/// The `INIT` flags that are supported by this implementation.
+const SUPPORTED_INIT_FLAGS: InitFlags = InitFlags::BIG_WRITES;
+
+/// A connection between the kernel and a FUSE daemon.
+///
+/// A connection is established by opening `/dev/fuse` and bound to a filesystem by
+/// mounting it. The filesystem queues requests in the connection, which are read by the
+/// daemon from the device file. The daemon then writes the replies to the device file.
+///
+/// A connection may also be established with a virtio filesystem device, in which case
+/// the requests are sent to the device and the replies are returned by the device.
+pub(super) struct FuseConn {
+    state: Mutex<ConnState>,
+    /// The virtio filesystem device, through which the requests are sent instead of
+    /// being read from `/dev/fuse`.
+    virtio_device: Option<Arc<FileSystemDevice>>,
+    /// The pollee of the device file, which is readable if there are requests to read.
+    pollee: Pollee,
+    /// The wait queue of the threads waiting for the replies or the initialization.
+    wait_queue: WaitQueue,
+    next_unique: AtomicU64,
+}
+
+struct ConnState {
+    status: ConnStatus,
+    /// The requests that have not been read by the daemon.
+    pending: VecDeque<Request>,
+    /// The requests that expect replies, indexed by their unique IDs.
+    processing: BTreeMap<u64, RequestState>,
+    /// The unique ID of the `INIT` request.
+    init_unique: u64,
+    init: Option<InitInfo>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConnStatus {
+    /// The connection has not been mounted, so the daemon cannot read requests.
+    Unmounted,
+    Mounted,
+    /// The connection has been aborted, as the daemon or the filesystem is gone.
+    Aborted,
+}
+
+struct Request {
+    unique: u64,
+    data: Vec<u8>,
+}
+
+enum RequestState {
+    /// The request is waiting to be read by the daemon.
+    Queued,
+    /// The request has been read by the daemon.
+    Sent,
+    Replied(Result<Vec<u8>>),
+    /// The requester has been interrupted, so the reply will be discarded.
+    Abandoned,
+}
+
+/// The parameters that are negotiated by `INIT`.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct InitInfo {
+    pub(super) minor: u32,
+    /// The maximum number of bytes in a `WRITE` request.
+    pub(super) max_write: usize,
+}
+
+impl FuseConn {
+    pub(super) fn new() -> Arc<Self> {
+        Self::new_with_device(None)
+    }
+
+    /// Creates a connection with a virtio filesystem device.
+    pub(super) fn new_virtio(device: Arc<FileSystemDevice>) -> Arc<Self> {
+        Self::new_with_device(Some(device))
+    }
+
+    fn new_with_device(virtio_device: Option<Arc<FileSystemDevice>>) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(ConnState {
+                status: ConnStatus::Unmounted,
+                pending: VecDeque::new(),
+                processing: BTreeMap::new(),
+                init_unique: 0,
+                init: None,
+            }),
+            virtio_device,
+            pollee: Pollee::new(),
+            wait_queue: WaitQueue::new(),
+            next_unique: AtomicU64::new(1),
+        })
+    }
+
+    /// Binds the connection to a mounted filesystem and sends the `INIT` request.
+    ///
+    /// The reply of `INIT` is handled asynchronously, as the daemon usually starts
+    /// serving the requests after the mount succeeds. The other requests wait until
+    /// the connection is initialized.
+    ///
+    /// For a virtio filesystem device, the host is already serving, so the reply of
+    /// `INIT` is waited for before this method returns.
+    pub(super) fn mount(&self) -> Result<()> {
+        let mut state = self.state.lock();
+        match state.status {
+            ConnStatus::Unmounted => {}
+            ConnStatus::Mounted => {
+                return_errno_with_message!(Errno::EINVAL, "the FUSE device is already mounted")
+            }
+            ConnStatus::Aborted => {
+                return_errno_with_message!(Errno::ENOTCONN, "the FUSE connection is aborted")
+            }
+        }
+
+        let init_in = InitIn {
+            major: KERNEL_VERSION,
+            minor: KERNEL_MINOR_VERSION,
+            max_readahead: MAX_READAHEAD,
+            flags: SUPPORTED_INIT_FLAGS.bits(),
+        };
+        let unique = self.alloc_unique();
+        let data = build_request(Opcode::Init, 0, unique, &[init_in.as_bytes()]);
+        state.status = ConnStatus::Mounted;
+        state.init_unique = unique;
+
+        if let Some(device) = self.virtio_device.as_ref() {
+            drop(state);
+
+            let init = self
+                .request_virtio(device, Opcode::Init, unique, &data)
+                .and_then(parse_init_reply);
+            match init {
+                Ok(init) => self.state.lock().init = Some(init),
+                Err(_) => {
+                    self.abort();
+                    return_errno_with_message!(Errno::EPROTO, "the virtio-fs device fails to init");
+                }
+            }
+            return Ok(());
+        }
+
+        state.processing.insert(unique, RequestState::Queued);
+        state.pending.push_back(Request { unique, data });
+        drop(state);
+
+        self.pollee.notify(IoEvents::IN);
+        Ok(())
+    }
+
+    /// Aborts the connection, failing all the requests.
+    ///
+    /// This happens when the device file is closed or the filesystem is unmounted.
+    pub(super) fn abort(&self) {
+        let mut state = self.state.lock();
+        let was_initialized = state.status == ConnStatus::Mounted && state.init.is_some();
+        state.status = ConnStatus::Aborted;
+        state.pending.clear();
+        state.processing.clear();
+        drop(state);
+
+        self.pollee.notify(IoEvents::IN | IoEvents::ERR);
+        self.wait_queue.wake_all();
+
+        // The host keeps the state of a virtio filesystem until it is destroyed, so that
+        // the device can be initialized again by a later mount.
+        if let Some(device) = self.virtio_device.as_ref()
+            && was_initialized
+        {
+            let unique = self.alloc_unique();
+            let data = build_request(Opcode::Destroy, 0, unique, &[]);
+            let _ = self.request_virtio(device, Opcode::Destroy, unique, &data);
+        }
+    }
+
+    /// Waits until the connection is initialized, and returns the negotiated parameters.
+    pub(super) fn wait_initialized(&self) -> Result<InitInfo> {
+        self.wait_queue.pause_until(|| {
+            let state = self.state.lock();
+            if state.status == ConnStatus::Aborted {
+                return Some(Err(Error::with_message(
+                    Errno::ENOTCONN,
+                    "the FUSE connection is aborted",
+                )));
+            }
+            state.init.map(Ok)
+        })?
+    }
+
+    /// Sends a request and waits for its reply.
+    ///
+    /// The arguments are concatenated to form the body of the request. The body of the
+    /// reply is returned. For the requests without replies, an empty body is returned
+    /// immediately.
+    pub(super) fn request(&self, opcode: Opcode, nodeid: u64, args: &[&[u8]]) -> Result<Vec<u8>> {
+        if opcode.has_reply() {
+            self.wait_initialized()?;
+        }
+
+        let unique = self.alloc_unique();
+        let data = build_request(opcode, nodeid, unique, args);
+        {
+            let mut state = self.state.lock();
+            if state.status != ConnStatus::Mounted {
+                return_errno_with_message!(Errno::ENOTCONN, "the FUSE connection is aborted");
+            }
+            if let Some(device) = self.virtio_device.as_ref() {
+                drop(state);
+                return self.request_virtio(device, opcode, unique, &data);
+            }
+            if opcode.has_reply() {
+                state.processing.insert(unique, RequestState::Queued);
+            }
+            state.pending.push_back(Request { unique, data });
+        }
+        self.pollee.notify(IoEvents::IN);
+
+        if !opcode.has_reply() {
+            return Ok(Vec::new());
+        }
+
+        let result = self.wait_queue.pause_until(|| {
+            let mut state = self.state.lock();
+            if state.status == ConnStatus::Aborted {
+                return Some(Err(Error::with_message(
+                    Errno::ENOTCONN,
+                    "the FUSE connection is aborted",
+                )));
+            }
+            match state.processing.remove(&unique) {
+                Some(RequestState::Replied(reply)) => Some(reply),
+                Some(request_state) => {
+                    state.processing.insert(unique, request_state);
+                    None
+                }
+                None => Some(Err(Error::with_message(
+                    Errno::ENOTCONN,
+                    "the FUSE request is lost",
+                ))),
+            }
+        });
+        match result {
+            Ok(reply) => reply,
+            Err(err) => {
+                self.interrupt(unique);
+                Err(err)
+            }
+        }
+    }
+
+    /// Sends a request to the virtio filesystem device and waits for its reply.
+    ///
+    /// The requests without replies are sent to the high-priority queue of the device.
+    fn request_virtio(
+        &self,
+        device: &FileSystemDevice,
+        opcode: Opcode,
+        unique: u64,
+        data: &[u8],
+    ) -> Result<Vec<u8>> {
+        if !opcode.has_reply() {
+            device
+                .send_hiprio(data)
+                .map_err(|_| Error::with_message(Errno::EIO, "the virtio-fs request fails"))?;
+            return Ok(Vec::new());
+        }
+
+        let reply = device
+            .request(data, size_of::<OutHeader>() + MAX_VIRTIO_REPLY)
+            .map_err(|_| Error::with_message(Errno::EIO, "the virtio-fs request fails"))?;
+
+        if reply.len() < size_of::<OutHeader>() {
+            return_errno_with_message!(Errno::EIO, "the virtio-fs reply is too short");
+        }
+        let header = OutHeader::from_first_bytes(&reply);
+        let len = header.len as usize;
+        if header.unique != unique || !(size_of::<OutHeader>()..=reply.len()).contains(&len) {
+            return_errno_with_message!(Errno::EIO, "the virtio-fs reply is invalid");
+        }
+        if header.error > 0 || header.error <= -1000 {
+            return_errno_with_message!(Errno::EIO, "the virtio-fs reply error is invalid");
+        }
+
+        reply_result(header.error, reply[size_of::<OutHeader>()..len].to_vec())
+    }
+
+    /// Cancels a request whose requester is interrupted by a signal.
+    ///
+    /// A request that has not been read is removed. Otherwise, an `INTERRUPT` request
+    /// is sent to the daemon and the reply will be discarded.
+    fn interrupt(&self, unique: u64) {
+        let mut state = self.state.lock();
+        match state.processing.get(&unique) {
+            Some(RequestState::Queued) => {
+                state.processing.remove(&unique);
+                state.pending.retain(|request| request.unique != unique);
+            }
+            Some(RequestState::Sent) => {
+                state.processing.insert(unique, RequestState::Abandoned);
+                drop(state);
+
+                let interrupt_in = InterruptIn { unique };
+                let _ = self.request(Opcode::Interrupt, 0, &[interrupt_in.as_bytes()]);
+            }
+            _ => {
+                state.processing.remove(&unique);
+            }
+        }
+    }
+
+    /// Reads a request into the buffer of the daemon.
+    pub(super) fn try_read_request(&self, writer: &mut VmWriter) -> Result<usize> {
+        let request = {
+            let mut state = self.state.lock();
+            match state.status {
+                ConnStatus::Unmounted => {
+                    return_errno_with_message!(Errno::EPERM, "the FUSE device is not mounted")
+                }
+                ConnStatus::Aborted => {
+                    return_errno_with_message!(Errno::ENODEV, "the FUSE connection is aborted")
+                }
+                ConnStatus::Mounted => {}
+            }
+            if writer.avail() < MIN_READ_BUFFER {
+                return_errno_with_message!(Errno::EINVAL, "the buffer is too small");
+            }
+
+            let Some(request) = state.pending.pop_front() else {
+                return_errno_with_message!(Errno::EAGAIN, "no requests are pending");
+            };
+            if state.pending.is_empty() {
+                self.pollee.invalidate();
+            }
+            request
+        };
+
+        let result = if request.data.len() > writer.avail() {
+            Err(Error::with_message(
+                Errno::EIO,
+                "the request is larger than the buffer",
+            ))
+        } else {
+            writer
+                .write_fallible(&mut VmReader::from(request.data.as_slice()))
+                .map_err(Error::from)
+        };
+
+        let mut state = self.state.lock();
+        if let Some(request_state) = state.processing.get_mut(&request.unique) {
+            // The requester sees an I/O error if the request cannot be delivered.
+            *request_state = match result {
+                Ok(_) => RequestState::Sent,
+                Err(_) => RequestState::Replied(Err(Error::new(Errno::EIO))),
+            };
+        }
+        drop(state);
+
+        if result.is_err() {
+            self.wait_queue.wake_all();
+        }
+        result
+    }
+
+    /// Writes a reply from the buffer of the daemon.
+    pub(super) fn write_reply(&self, reader: &mut VmReader) -> Result<usize> {
+        let len = reader.remain();
+        if len < size_of::<OutHeader>() {
+            return_errno_with_message!(Errno::EINVAL, "the reply is too short");
+        }
+        let header = reader.read_val::<OutHeader>()?;
+        if header.len as usize != len {
+            return_errno_with_message!(Errno::EINVAL, "the reply length mismatches");
+        }
+        if header.unique == 0 {
+            return_errno_with_message!(Errno::EINVAL, "notifications are not supported");
+        }
+        if header.error > 0 || header.error <= -1000 {
+            return_errno_with_message!(Errno::EINVAL, "the reply error is invalid");
+        }
+
+        let mut body = vec![0u8; len - size_of::<OutHeader>()];
+        reader.read_fallible(&mut VmWriter::from(body.as_mut_slice()))?;
+        let reply = reply_result(header.error, body);
+
+        let mut state = self.state.lock();
+        if state.status == ConnStatus::Aborted {
+            return_errno_with_message!(Errno::ENODEV, "the FUSE connection is aborted");
+        }
+
+        if header.unique == state.init_unique && state.init.is_none() {
+            state.processing.remove(&header.unique);
+            match reply.and_then(parse_init_reply) {
+                Ok(init) => state.init = Some(init),
+                Err(_) => {
+                    drop(state);
+                    self.abort();
+                    return_errno_with_message!(Errno::EPROTO, "the FUSE daemon fails to init");
+                }
+            }
+        } else {
+            match state.processing.get(&header.unique) {
+                Some(RequestState::Sent) => {
+                    state
+                        .processing
+                        .insert(header.unique, RequestState::Replied(reply));
+                }
+                Some(RequestState::Abandoned) => {
+                    state.processing.remove(&header.unique);
+                }
+                _ => return_errno_with_message!(Errno::ENOENT, "no request matches the reply"),
+            }
+        }
+        drop(state);
+
+        self.wait_queue.wake_all();
+        Ok(len)
+    }
+
+    /// Returns whether the connection is aborted.
+    pub(super) fn is_aborted(&self) -> bool {
+        self.state.lock().status == ConnStatus::Aborted
+    }
+
+    /// Returns whether there are requests to read.
+    pub(super) fn has_pending(&self) -> bool {
+        !self.state.lock().pending.is_empty()
+    }
+
+    pub(super) fn pollee(&self) -> &Pollee {
+        &self.pollee
+    }
+
+    /// Returns whether the connection is established with a virtio filesystem device.
+    pub(super) fn is_virtio(&self) -> bool {
+        self.virtio_device.is_some()
+    }
+
+    fn alloc_unique(&self) -> u64 {
+        self.next_unique.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+/// Builds a request with the credentials of the current thread.
+fn build_request(opcode: Opcode, nodeid: u64, unique: u64, args: &[&[u8]]) -> Vec<u8> {
+    let (uid, gid, pid) = match Task::current()
+        .as_ref()
+        .and_then(|task| task.as_posix_thread())
+    {
+        Some(thread) => {
+            let credentials = thread.credentials();
+            (
+                credentials.fsuid().into(),
+                credentials.fsgid().into(),
+                thread.process().pid(),
+            )
+        }
+        None => (0, 0, 0),
+    };
+
+    let len = size_of::<InHeader>() + args.iter().map(|arg| arg.len()).sum::<usize>();
+    let header = InHeader {
+        len: len as u32,
+        opcode: opcode as u32,
+        unique,
+        nodeid,
+        uid,
+        gid,
+        pid,
+        total_extlen: 0,
+        padding: 0,
+    };
+
+    let mut data = Vec::with_capacity(len);
+    data.extend_from_slice(header.as_bytes());
+    for arg in args {
+        data.extend_from_slice(arg);
+    }
+    data
+}
+
+/// Converts the error number and the body of a reply to the result of the request.
+fn reply_result(error: i32, body: Vec<u8>) -> Result<Vec<u8>> {
+    if error == 0 {
+        Ok(body)
+    } else {
+        Err(Error::new(Errno::try_from(-error).unwrap_or(Errno::EIO)))
+    }
+}
+
+fn parse_init_reply(body: Vec<u8>) -> Result<InitInfo> {
+    // The daemons of old versions reply with shorter messages.
+    let mut init_out = InitOut::new_zeroed();
+    let len = body.len().min(size_of::<InitOut>());
+    init_out.as_mut_bytes()[..len].copy_from_slice(&body[..len]);
+
+    if init_out.major != KERNEL_VERSION {
+        return_errno_with_message!(Errno::EPROTO, "the FUSE major version mismatches");
+    }
+
+    let flags = InitFlags::from_bits_truncate(init_out.flags) & SUPPORTED_INIT_FLAGS;
+    let max_write = if flags.contains(InitFlags::BIG_WRITES) {
+        (init_out.max_write as usize).clamp(DEFAULT_MAX_WRITE, MAX_MAX_WRITE)
+    } else {
+        DEFAULT_MAX_WRITE
+    };
+
+    Ok(InitInfo {
+        minor: init_out.minor.min(KERNEL_MINOR_VERSION),
+        max_write,
+    })
+}
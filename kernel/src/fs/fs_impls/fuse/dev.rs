@@ -0,0 +1,94 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::conn::FuseConn;
+use crate::{
+    events::IoEvents,
+    fs::{
+        file::{FileIo, StatusFlags},
+        vfs::inode::InodeIo,
+    },
+    prelude::*,
+    process::signal::{PollHandle, Pollable},
+};
+
+/// An opened file of `/dev/fuse`, through which a FUSE daemon serves a filesystem.
+///
+/// Each opened file owns a connection. The connection is aborted when the file is
+/// closed, after which the requests of the filesystem fail with `ENOTCONN`.
+pub struct FuseDevFile {
+    conn: Arc<FuseConn>,
+}
+
+impl FuseDevFile {
+    pub fn new() -> Self {
+        Self {
+            conn: FuseConn::new(),
+        }
+    }
+
+    pub(super) fn conn(&self) -> &Arc<FuseConn> {
+        &self.conn
+    }
+}
+
+impl Default for FuseDevFile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for FuseDevFile {
+    fn drop(&mut self) {
+        self.conn.abort();
+    }
+}
+
+impl Pollable for FuseDevFile {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.conn.pollee().poll_with(mask, poller, || {
+            if self.conn.is_aborted() {
+                return IoEvents::IN | IoEvents::OUT | IoEvents::ERR;
+            }
+
+            let mut events = IoEvents::OUT;
+            if self.conn.has_pending() {
+                events |= IoEvents::IN;
+            }
+            events
+        })
+    }
+}
+
+impl InodeIo for FuseDevFile {
+    fn read_at(
+        &self,
+        _offset: usize,
+        writer: &mut VmWriter,
+        status_flags: StatusFlags,
+    ) -> Result<usize> {
+        if status_flags.contains(StatusFlags::O_NONBLOCK) {
+            self.conn.try_read_request(writer)
+        } else {
+            self.wait_events(IoEvents::IN, None, || self.conn.try_read_request(writer))
+        }
+    }
+
+    fn write_at(
+        &self,
+        _offset: usize,
+        reader: &mut VmReader,
+        _status_flags: StatusFlags,
+    ) -> Result<usize> {
+        self.conn.write_reply(reader)
+    }
+}
+
+impl FileIo for FuseDevFile {
+    fn check_seekable(&self) -> Result<()> {
+        return_errno_with_message!(Errno::ESPIPE, "seek is not supported")
+    }
+
+    fn is_offset_aware(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,358 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use ostd::task::Task;
+
+use super::{
+    abi::{Opcode, ROOT_ID, StatfsOut, parse_reply},
+    conn::FuseConn,
+    dev::FuseDevFile,
+    inode::FuseInode,
+};
+use crate::{
+    fs::{
+        file::{InodeHandle, InodeType, file_table::FileDesc},
+        pseudofs::AnonDeviceId,
+        utils::NAME_MAX,
+        vfs::{
+            file_system::{FileSystem, FsEventSubscriberStats, FsFlags, SuperBlock},
+            inode::Inode,
+            registry::{FsProperties, FsType},
+        },
+    },
+    prelude::*,
+    process::{Gid, Uid},
+};
+
+/// The magic number of FUSE in `statfs`.
+const FUSE_SUPER_MAGIC: u64 = 0x65735546;
+
+/// The default block size, which is reported if the daemon does not give one.
+const DEFAULT_BLOCK_SIZE: usize = 4096;
+
+/// A filesystem whose operations are served by a userspace daemon through `/dev/fuse`.
+pub struct FuseFs {
+    conn: Arc<FuseConn>,
+    options: FuseMountOptions,
+    anon_device_id: AnonDeviceId,
+    /// The inodes that are in use, indexed by their node IDs.
+    inodes: Mutex<BTreeMap<u64, Weak<FuseInode>>>,
+    root: Arc<FuseInode>,
+    fs_event_subscriber_stats: FsEventSubscriberStats,
+}
+
+impl FuseFs {
+    fn new(conn: Arc<FuseConn>, options: FuseMountOptions) -> Result<Arc<Self>> {
+        let anon_device_id = AnonDeviceId::acquire()
+            .ok_or_else(|| Error::with_message(Errno::EMFILE, "no device ID is available"))?;
+
+        conn.mount()?;
+
+        let fs = Arc::new_cyclic(|weak_fs| Self {
+            root: FuseInode::new_root(weak_fs.clone(), conn.clone(), &options),
+            conn,
+            options,
+            anon_device_id,
+            inodes: Mutex::new(BTreeMap::new()),
+            fs_event_subscriber_stats: FsEventSubscriberStats::new(),
+        });
+        fs.inodes.lock().insert(ROOT_ID, Arc::downgrade(&fs.root));
+        Ok(fs)
+    }
+
+    pub(super) fn options(&self) -> &FuseMountOptions {
+        &self.options
+    }
+
+    pub(super) fn device_id(&self) -> device_id::DeviceId {
+        self.anon_device_id.id()
+    }
+
+    /// Returns the inode of a node that is looked up by the daemon.
+    ///
+    /// Each successful lookup increases the lookup count of the node, which is given
+    /// back to the daemon by `FORGET` when the inode is dropped. So the lookup count of
+    /// the returned inode is increased, whether it is a loaded one or a new one.
+    pub(super) fn get_or_insert_inode(
+        self: &Arc<Self>,
+        nodeid: u64,
+        new_inode: impl FnOnce() -> Arc<FuseInode>,
+    ) -> Arc<FuseInode> {
+        let mut inodes = self.inodes.lock();
+        if let Some(inode) = inodes.get(&nodeid).and_then(Weak::upgrade) {
+            inode.inc_nlookup();
+            return inode;
+        }
+
+        let inode = new_inode();
+        inodes.insert(nodeid, Arc::downgrade(&inode));
+        inode
+    }
+
+    /// Removes a dropped inode from the inodes in use.
+    pub(super) fn remove_inode(&self, nodeid: u64) {
+        let mut inodes = self.inodes.lock();
+        // The node may have been looked up again with a new inode.
+        if inodes
+            .get(&nodeid)
+            .is_some_and(|inode| inode.strong_count() == 0)
+        {
+            inodes.remove(&nodeid);
+        }
+    }
+}
+
+impl Drop for FuseFs {
+    fn drop(&mut self) {
+        self.conn.abort();
+    }
+}
+
+impl FileSystem for FuseFs {
+    fn name(&self) -> &'static str {
+        if self.conn.is_virtio() {
+            "virtiofs"
+        } else {
+            "fuse"
+        }
+    }
+
+    fn sync(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn root_inode(&self) -> Arc<dyn Inode> {
+        self.root.clone()
+    }
+
+    fn sb(&self) -> SuperBlock {
+        let mut sb = SuperBlock::new(
+            FUSE_SUPER_MAGIC,
+            self.options.blksize,
+            NAME_MAX,
+            self.anon_device_id.id(),
+        );
+
+        // The daemon may be unresponsive, in which case the zero statistics are reported.
+        let Ok(reply) = self.conn.request(Opcode::Statfs, ROOT_ID, &[]) else {
+            return sb;
+        };
+        let Ok(statfs_out) = parse_reply::<StatfsOut>(&reply) else {
+            return sb;
+        };
+        sb.bsize = statfs_out.bsize as usize;
+        sb.frsize = statfs_out.frsize as usize;
+        sb.blocks = statfs_out.blocks as usize;
+        sb.bfree = statfs_out.bfree as usize;
+        sb.bavail = statfs_out.bavail as usize;
+        sb.files = statfs_out.files as usize;
+        sb.ffree = statfs_out.ffree as usize;
+        sb.namelen = statfs_out.namelen as usize;
+        sb
+    }
+
+    fn fs_event_subscriber_stats(&self) -> &FsEventSubscriberStats {
+        &self.fs_event_subscriber_stats
+    }
+}
+
+/// The mount options of a FUSE filesystem.
+#[derive(Debug, Clone)]
+pub(super) struct FuseMountOptions {
+    /// The file descriptor of the opened `/dev/fuse`.
+    fd: FileDesc,
+    /// The type of the root inode.
+    pub(super) root_type: InodeType,
+    /// The user that mounts the filesystem.
+    pub(super) user_id: Uid,
+    /// The group that mounts the filesystem.
+    pub(super) group_id: Gid,
+    /// Whether the permissions are checked by the kernel instead of the daemon.
+    pub(super) default_permissions: bool,
+    /// Whether the users other than the mounting user can access the filesystem.
+    pub(super) allow_other: bool,
+    /// The maximum number of bytes in a `READ` request.
+    pub(super) max_read: usize,
+    pub(super) blksize: usize,
+}
+
+impl FuseMountOptions {
+    /// Returns the mount options of a virtio filesystem.
+    ///
+    /// The filesystem is shared by the host, so the permissions are checked by the
+    /// kernel and all the users can access it.
+    fn new_virtio() -> Self {
+        Self {
+            fd: 0,
+            root_type: InodeType::Dir,
+            user_id: Uid::new_root(),
+            group_id: Gid::new_root(),
+            default_permissions: true,
+            allow_other: true,
+            max_read: usize::MAX,
+            blksize: DEFAULT_BLOCK_SIZE,
+        }
+    }
+
+    fn parse(args: Option<&CStr>) -> Result<Self> {
+        let Some(args) = args else {
+            return_errno_with_message!(Errno::EINVAL, "no fuse options are given");
+        };
+        let args = args.to_string_lossy();
+
+        let mut fd = None;
+        let mut root_type = None;
+        let mut user_id = None;
+        let mut group_id = None;
+        let mut options = Self {
+            fd: 0,
+            root_type: InodeType::Dir,
+            user_id: Uid::new_root(),
+            group_id: Gid::new_root(),
+            default_permissions: false,
+            allow_other: false,
+            max_read: usize::MAX,
+            blksize: DEFAULT_BLOCK_SIZE,
+        };
+
+        for entry in args.split(',').filter(|entry| !entry.is_empty()) {
+            let (key, value) = entry.split_once('=').unwrap_or((entry, ""));
+            match key {
+                "fd" => fd = Some(value.parse::<FileDesc>().map_err(|_| invalid_option())?),
+                "rootmode" => {
+                    let mode = u16::from_str_radix(value, 8).map_err(|_| invalid_option())?;
+                    root_type = Some(InodeType::from_raw_mode(mode)?);
+                }
+                "user_id" => user_id = Some(Uid::new(parse_number(value)?)),
+                "group_id" => group_id = Some(Gid::new(parse_number(value)?)),
+                "default_permissions" => options.default_permissions = true,
+                "allow_other" => options.allow_other = true,
+                "max_read" => options.max_read = parse_number(value)? as usize,
+                "blksize" => {
+                    let blksize = parse_number(value)? as usize;
+                    if !blksize.is_power_of_two() || !(512..=PAGE_SIZE).contains(&blksize) {
+                        return Err(invalid_option());
+                    }
+                    options.blksize = blksize;
+                }
+                _ => return_errno_with_message!(Errno::EINVAL, "unknown fuse option"),
+            }
+        }
+
+        let (Some(fd), Some(root_type), Some(user_id), Some(group_id)) =
+            (fd, root_type, user_id, group_id)
+        else {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "fd, rootmode, user_id and group_id are required"
+            );
+        };
+        options.fd = fd;
+        options.root_type = root_type;
+        options.user_id = user_id;
+        options.group_id = group_id;
+        Ok(options)
+    }
+}
+
+fn parse_number(value: &str) -> Result<u32> {
+    value.parse::<u32>().map_err(|_| invalid_option())
+}
+
+fn invalid_option() -> Error {
+    Error::with_message(Errno::EINVAL, "invalid fuse option value")
+}
+
+pub(super) struct FuseType;
+
+impl FsType for FuseType {
+    fn name(&self) -> &'static str {
+        "fuse"
+    }
+
+    fn properties(&self) -> FsProperties {
+        FsProperties::HAS_SUBTYPE
+    }
+
+    fn create(
+        &self,
+        _flags: FsFlags,
+        _source: Option<&str>,
+        args: Option<CString>,
+        _disk: Option<Arc<dyn aster_block::BlockDevice>>,
+    ) -> Result<Arc<dyn FileSystem>> {
+        let options = FuseMountOptions::parse(args.as_deref())?;
+
+        let file = {
+            let task = Task::current().unwrap();
+            let thread_local = task.as_thread_local().unwrap();
+            let file_table = thread_local.borrow_file_table();
+            let file_table_locked = file_table.unwrap().read();
+            file_table_locked.get_file(options.fd)?.clone()
+        };
+        let conn = file
+            .downcast_ref::<InodeHandle>()
+            .map(|inode_handle| inode_handle.downcast_file_io::<FuseDevFile>())
+            .transpose()?
+            .flatten()
+            .map(|dev_file| dev_file.conn().clone())
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "the fd is not a FUSE device"))?;
+
+        let fs = FuseFs::new(conn, options)?;
+        Ok(fs)
+    }
+
+    fn sysnode(&self) -> Option<Arc<dyn aster_systree::SysNode>> {
+        None
+    }
+}
+
+/// The type of the filesystems that are shared by the host through virtio filesystem
+/// devices, which are mounted with the tags of the devices as the sources.
+pub(super) struct VirtioFsType;
+
+/// The mounted virtio filesystems, indexed by the tags.
+///
+/// A device can only be initialized once at a time, so the mounts of the same tag
+/// share the filesystem.
+static VIRTIO_FILESYSTEMS: Mutex<BTreeMap<String, Weak<FuseFs>>> = Mutex::new(BTreeMap::new());
+
+impl FsType for VirtioFsType {
+    fn name(&self) -> &'static str {
+        "virtiofs"
+    }
+
+    fn properties(&self) -> FsProperties {
+        FsProperties::empty()
+    }
+
+    fn create(
+        &self,
+        _flags: FsFlags,
+        source: Option<&str>,
+        args: Option<CString>,
+        _disk: Option<Arc<dyn aster_block::BlockDevice>>,
+    ) -> Result<Arc<dyn FileSystem>> {
+        let Some(tag) = source else {
+            return_errno_with_message!(Errno::EINVAL, "the tag of virtiofs is not given");
+        };
+        if args.is_some_and(|args| !args.is_empty()) {
+            return_errno_with_message!(Errno::EINVAL, "virtiofs options are not supported");
+        }
+
+        let mut filesystems = VIRTIO_FILESYSTEMS.lock();
+        if let Some(fs) = filesystems.get(tag).and_then(Weak::upgrade) {
+            return Ok(fs);
+        }
+
+        let device = aster_virtio::device::filesystem::get_device(tag).ok_or_else(|| {
+            Error::with_message(Errno::ENOENT, "no virtio-fs device matches the tag")
+        })?;
+        let fs = FuseFs::new(FuseConn::new_virtio(device), FuseMountOptions::new_virtio())?;
+        filesystems.insert(tag.to_string(), Arc::downgrade(&fs));
+        Ok(fs)
+    }
+
+    fn sysnode(&self) -> Option<Arc<dyn aster_systree::SysNode>> {
+        None
+    }
+}
@@ -0,0 +1,886 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use device_id::DeviceId;
+use ostd::task::Task;
+
+use super::{
+    abi::{
+        Attr, AttrOut, Dirent, EntryOut, FallocateIn, FlushIn, ForgetIn, FsyncIn, GetattrIn,
+        LinkIn, MkdirIn, MknodIn, Opcode, OpenIn, OpenOut, OpenOutFlags, ROOT_ID, ReadIn,
+        ReleaseIn, RenameIn, SetattrIn, SetattrValid, WriteIn, WriteOut, parse_reply,
+    },
+    conn::FuseConn,
+    fs::{FuseFs, FuseMountOptions},
+};
+use crate::{
+    events::IoEvents,
+    fs::{
+        file::{AccessMode, FileIo, InodeMode, InodeType, Permission, StatusFlags},
+        utils::DirentVisitor,
+        vfs::{
+            file_system::FileSystem,
+            inode::{
+                Extension, FallocMode, Inode, InodeIo, Metadata, MknodType, SymbolicLink,
+                check_dac_permission,
+            },
+        },
+    },
+    prelude::*,
+    process::{
+        Gid, Uid,
+        posix_thread::AsPosixThread,
+        signal::{PollHandle, Pollable},
+    },
+    time::clocks::MonotonicCoarseClock,
+};
+
+/// The maximum number of bytes in a `READ` request.
+const MAX_READ_SIZE: usize = 32 * PAGE_SIZE;
+
+/// The raw flags of `fallocate`.
+const FALLOC_FL_KEEP_SIZE: u32 = 0x01;
+const FALLOC_FL_PUNCH_HOLE: u32 = 0x02;
+const FALLOC_FL_COLLAPSE_RANGE: u32 = 0x08;
+const FALLOC_FL_ZERO_RANGE: u32 = 0x10;
+const FALLOC_FL_INSERT_RANGE: u32 = 0x20;
+const FALLOC_FL_UNSHARE_RANGE: u32 = 0x40;
+
+/// An inode of a FUSE filesystem, which is a node of the daemon.
+pub(super) struct FuseInode {
+    /// The node ID, which is zero for a symbolic link that has not been created.
+    nodeid: u64,
+    ino: u64,
+    type_: InodeType,
+    attr: Mutex<CachedAttr>,
+    /// The number of the lookups that have not been forgotten.
+    nlookup: AtomicU64,
+    /// The directory and the name of a symbolic link that has not been created.
+    ///
+    /// The VFS creates a symbolic link before writing its target, but the daemon
+    /// creates it with the target by `SYMLINK`. So the symbolic link is created when
+    /// its target is written.
+    pending_symlink: Mutex<Option<(u64, String)>>,
+    conn: Arc<FuseConn>,
+    fs: Weak<FuseFs>,
+    this: Weak<FuseInode>,
+    extension: Extension,
+}
+
+struct CachedAttr {
+    attr: Attr,
+    /// The monotonic time after which the attributes should be fetched again.
+    valid_until: Duration,
+}
+
+impl FuseInode {
+    pub(super) fn new_root(
+        fs: Weak<FuseFs>,
+        conn: Arc<FuseConn>,
+        options: &FuseMountOptions,
+    ) -> Arc<Self> {
+        let mut attr = Attr::new_zeroed();
+        attr.ino = ROOT_ID;
+        attr.mode = options.root_type as u32 | 0o755;
+        attr.nlink = 1;
+        attr.uid = options.user_id.into();
+        attr.gid = options.group_id.into();
+
+        // The real attributes are fetched with `GETATTR` when they are used.
+        Self::new(fs, conn, ROOT_ID, attr, Duration::ZERO)
+    }
+
+    fn new(
+        fs: Weak<FuseFs>,
+        conn: Arc<FuseConn>,
+        nodeid: u64,
+        attr: Attr,
+        valid_until: Duration,
+    ) -> Arc<Self> {
+        let type_ = InodeType::from_raw_mode(attr.mode as u16).unwrap_or(InodeType::Unknown);
+        Arc::new_cyclic(|weak_self| Self {
+            nodeid,
+            ino: attr.ino,
+            type_,
+            attr: Mutex::new(CachedAttr { attr, valid_until }),
+            nlookup: AtomicU64::new(1),
+            pending_symlink: Mutex::new(None),
+            conn,
+            fs,
+            this: weak_self.clone(),
+            extension: Extension::new(),
+        })
+    }
+
+    pub(super) fn inc_nlookup(&self) {
+        self.nlookup.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn fs(&self) -> Arc<FuseFs> {
+        self.fs.upgrade().unwrap()
+    }
+
+    fn request(&self, opcode: Opcode, args: &[&[u8]]) -> Result<Vec<u8>> {
+        if self.nodeid == 0 {
+            return_errno_with_message!(Errno::ENOENT, "the symbolic link is not created");
+        }
+        self.conn.request(opcode, self.nodeid, args)
+    }
+
+    fn check_dir(&self) -> Result<()> {
+        if self.type_ != InodeType::Dir {
+            return_errno!(Errno::ENOTDIR);
+        }
+        Ok(())
+    }
+
+    /// Returns the inode of an entry that is replied by the daemon.
+    fn new_entry(&self, reply: &[u8]) -> Result<Arc<FuseInode>> {
+        let entry_out = parse_reply::<EntryOut>(reply)?;
+        // A zero node ID means a negative entry, i.e., the file does not exist.
+        if entry_out.nodeid == 0 {
+            return_errno!(Errno::ENOENT);
+        }
+        if entry_out.nodeid == ROOT_ID {
+            return_errno_with_message!(Errno::EIO, "the entry is the root directory");
+        }
+
+        let valid_until = expiry(entry_out.attr_valid, entry_out.attr_valid_nsec);
+        let fs = self.fs();
+        let inode = fs.get_or_insert_inode(entry_out.nodeid, || {
+            Self::new(
+                self.fs.clone(),
+                self.conn.clone(),
+                entry_out.nodeid,
+                entry_out.attr,
+                valid_until,
+            )
+        });
+        if inode.type_ != InodeType::from_raw_mode(entry_out.attr.mode as u16)? {
+            return_errno_with_message!(Errno::EIO, "the type of the node is changed");
+        }
+        inode.update_attr(entry_out.attr, valid_until);
+        Ok(inode)
+    }
+
+    fn update_attr(&self, attr: Attr, valid_until: Duration) {
+        *self.attr.lock() = CachedAttr { attr, valid_until };
+    }
+
+    /// Marks the cached attributes as stale, e.g., after the data are written.
+    fn invalidate_attr(&self) {
+        self.attr.lock().valid_until = Duration::ZERO;
+    }
+
+    /// Returns the attributes, which are fetched with `GETATTR` if the cached ones expire.
+    fn attr(&self) -> Result<Attr> {
+        {
+            let cached = self.attr.lock();
+            if self.nodeid == 0 || MonotonicCoarseClock::get().read_time() < cached.valid_until {
+                return Ok(cached.attr);
+            }
+        }
+
+        let getattr_in = GetattrIn::new_zeroed();
+        let reply = self.request(Opcode::Getattr, &[getattr_in.as_bytes()])?;
+        let attr_out = parse_reply::<AttrOut>(&reply)?;
+        self.update_attr(
+            attr_out.attr,
+            expiry(attr_out.attr_valid, attr_out.attr_valid_nsec),
+        );
+        Ok(attr_out.attr)
+    }
+
+    /// Returns the attributes, or the cached ones if the daemon fails to reply.
+    fn attr_or_cached(&self) -> Attr {
+        self.attr().unwrap_or_else(|_| self.attr.lock().attr)
+    }
+
+    fn setattr(&self, setattr_in: SetattrIn) -> Result<()> {
+        let reply = self.request(Opcode::Setattr, &[setattr_in.as_bytes()])?;
+        let attr_out = parse_reply::<AttrOut>(&reply)?;
+        self.update_attr(
+            attr_out.attr,
+            expiry(attr_out.attr_valid, attr_out.attr_valid_nsec),
+        );
+        Ok(())
+    }
+
+    fn set_time(&self, valid: SetattrValid, time: Duration) -> Result<()> {
+        let mut setattr_in = SetattrIn::new_zeroed();
+        setattr_in.valid = valid.bits();
+        let (secs, nsecs) = (time.as_secs(), time.subsec_nanos());
+        if valid.contains(SetattrValid::ATIME) {
+            (setattr_in.atime, setattr_in.atimensec) = (secs, nsecs);
+        }
+        if valid.contains(SetattrValid::MTIME) {
+            (setattr_in.mtime, setattr_in.mtimensec) = (secs, nsecs);
+        }
+        if valid.contains(SetattrValid::CTIME) {
+            (setattr_in.ctime, setattr_in.ctimensec) = (secs, nsecs);
+        }
+        self.setattr(setattr_in)
+    }
+
+    /// Opens a handle of the daemon.
+    fn open_handle(&self, flags: u32) -> Result<OpenOut> {
+        let opcode = if self.type_ == InodeType::Dir {
+            Opcode::Opendir
+        } else {
+            Opcode::Open
+        };
+        let open_in = OpenIn {
+            flags,
+            open_flags: 0,
+        };
+        let reply = self.request(opcode, &[open_in.as_bytes()])?;
+        parse_reply::<OpenOut>(&reply)
+    }
+
+    fn release_handle(&self, fh: u64, flags: u32) {
+        let opcode = if self.type_ == InodeType::Dir {
+            Opcode::Releasedir
+        } else {
+            Opcode::Release
+        };
+        let release_in = ReleaseIn {
+            fh,
+            flags,
+            release_flags: 0,
+            lock_owner: 0,
+        };
+        let _ = self.request(opcode, &[release_in.as_bytes()]);
+    }
+
+    /// Runs an operation with a handle that is opened only for it.
+    ///
+    /// This is used by the operations of the inodes that are not done through opened
+    /// files, but need handles in the FUSE protocol.
+    fn with_handle<R>(&self, flags: u32, op: impl FnOnce(u64) -> Result<R>) -> Result<R> {
+        let fh = self.open_handle(flags)?.fh;
+        let result = op(fh);
+        self.release_handle(fh, flags);
+        result
+    }
+
+    fn read_handle(&self, fh: u64, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let max_read = self.fs().options().max_read.min(MAX_READ_SIZE);
+
+        let mut read_len = 0;
+        while writer.has_avail() {
+            let size = writer.avail().min(max_read);
+            let read_in = ReadIn {
+                fh,
+                offset: (offset + read_len) as u64,
+                size: size as u32,
+                read_flags: 0,
+                lock_owner: 0,
+                flags: 0,
+                padding: 0,
+            };
+            let reply = self.request(Opcode::Read, &[read_in.as_bytes()])?;
+            if reply.len() > size {
+                return_errno_with_message!(Errno::EIO, "the FUSE daemon reads too many bytes");
+            }
+            writer.write_fallible(&mut reply.as_slice().into())?;
+
+            read_len += reply.len();
+            // A short read means the end of the file.
+            if reply.len() < size {
+                break;
+            }
+        }
+        Ok(read_len)
+    }
+
+    fn write_handle(&self, fh: u64, offset: usize, reader: &mut VmReader) -> Result<usize> {
+        let max_write = self.conn.wait_initialized()?.max_write;
+
+        let mut written_len = 0;
+        while reader.has_remain() {
+            let size = reader.remain().min(max_write);
+            let mut data = vec![0u8; size];
+            reader.read_fallible(&mut VmWriter::from(data.as_mut_slice()))?;
+
+            let write_in = WriteIn {
+                fh,
+                offset: (offset + written_len) as u64,
+                size: size as u32,
+                write_flags: 0,
+                lock_owner: 0,
+                flags: 0,
+                padding: 0,
+            };
+            let reply = self.request(Opcode::Write, &[write_in.as_bytes(), &data]);
+            self.invalidate_attr();
+            let write_out = match reply.and_then(|reply| parse_reply::<WriteOut>(&reply)) {
+                Ok(write_out) => write_out,
+                Err(_) if written_len > 0 => break,
+                Err(err) => return Err(err),
+            };
+            if write_out.size as usize > size {
+                return_errno_with_message!(Errno::EIO, "the FUSE daemon writes too many bytes");
+            }
+
+            written_len += write_out.size as usize;
+            if (write_out.size as usize) < size {
+                break;
+            }
+        }
+        Ok(written_len)
+    }
+
+    fn mknod_raw(&self, name: &str, mode: u32, rdev: u32) -> Result<Arc<dyn Inode>> {
+        self.check_dir()?;
+
+        // The umask has been applied by the VFS.
+        let mknod_in = MknodIn {
+            mode,
+            rdev,
+            umask: 0,
+            padding: 0,
+        };
+        let reply = self.request(Opcode::Mknod, &[mknod_in.as_bytes(), name.as_bytes(), &[0]])?;
+        Ok(self.new_entry(&reply)?)
+    }
+}
+
+impl Drop for FuseInode {
+    fn drop(&mut self) {
+        if self.nodeid == 0 || self.nodeid == ROOT_ID {
+            return;
+        }
+
+        if let Some(fs) = self.fs.upgrade() {
+            fs.remove_inode(self.nodeid);
+        }
+        let forget_in = ForgetIn {
+            nlookup: self.nlookup.load(Ordering::Relaxed),
+        };
+        let _ = self
+            .conn
+            .request(Opcode::Forget, self.nodeid, &[forget_in.as_bytes()]);
+    }
+}
+
+impl InodeIo for FuseInode {
+    fn read_at(
+        &self,
+        offset: usize,
+        writer: &mut VmWriter,
+        _status_flags: StatusFlags,
+    ) -> Result<usize> {
+        if self.type_ == InodeType::Dir {
+            return_errno!(Errno::EISDIR);
+        }
+        self.with_handle(AccessMode::O_RDONLY as u32, |fh| {
+            self.read_handle(fh, offset, writer)
+        })
+    }
+
+    fn write_at(
+        &self,
+        offset: usize,
+        reader: &mut VmReader,
+        _status_flags: StatusFlags,
+    ) -> Result<usize> {
+        if self.type_ == InodeType::Dir {
+            return_errno!(Errno::EISDIR);
+        }
+        self.with_handle(AccessMode::O_WRONLY as u32, |fh| {
+            self.write_handle(fh, offset, reader)
+        })
+    }
+}
+
+impl Inode for FuseInode {
+    fn size(&self) -> usize {
+        self.attr_or_cached().size as usize
+    }
+
+    fn resize(&self, new_size: usize) -> Result<()> {
+        let mut setattr_in = SetattrIn::new_zeroed();
+        setattr_in.valid = SetattrValid::SIZE.bits();
+        setattr_in.size = new_size as u64;
+        self.setattr(setattr_in)
+    }
+
+    fn metadata(&self) -> Metadata {
+        let attr = self.attr_or_cached();
+        let blksize = if attr.blksize == 0 {
+            self.fs().options().blksize
+        } else {
+            attr.blksize as usize
+        };
+
+        Metadata {
+            ino: self.ino,
+            size: attr.size as usize,
+            optimal_block_size: blksize,
+            nr_sectors_allocated: attr.blocks as usize,
+            last_access_at: Duration::new(attr.atime, attr.atimensec),
+            last_modify_at: Duration::new(attr.mtime, attr.mtimensec),
+            last_meta_change_at: Duration::new(attr.ctime, attr.ctimensec),
+            type_: self.type_,
+            mode: InodeMode::from_bits_truncate(attr.mode as u16),
+            nr_hard_links: attr.nlink as usize,
+            uid: Uid::new(attr.uid),
+            gid: Gid::new(attr.gid),
+            container_dev_id: self.fs().device_id(),
+            self_dev_id: match self.type_ {
+                InodeType::BlockDevice | InodeType::CharDevice => {
+                    DeviceId::from_encoded_u64(attr.rdev as u64)
+                }
+                _ => None,
+            },
+        }
+    }
+
+    fn ino(&self) -> u64 {
+        self.ino
+    }
+
+    fn type_(&self) -> InodeType {
+        self.type_
+    }
+
+    fn mode(&self) -> Result<InodeMode> {
+        Ok(InodeMode::from_bits_truncate(self.attr()?.mode as u16))
+    }
+
+    fn set_mode(&self, mode: InodeMode) -> Result<()> {
+        let mut setattr_in = SetattrIn::new_zeroed();
+        setattr_in.valid = SetattrValid::MODE.bits();
+        setattr_in.mode = self.type_ as u32 | mode.bits() as u32;
+        self.setattr(setattr_in)
+    }
+
+    fn owner(&self) -> Result<Uid> {
+        Ok(Uid::new(self.attr()?.uid))
+    }
+
+    fn set_owner(&self, uid: Uid) -> Result<()> {
+        let mut setattr_in = SetattrIn::new_zeroed();
+        setattr_in.valid = SetattrValid::UID.bits();
+        setattr_in.uid = uid.into();
+        self.setattr(setattr_in)
+    }
+
+    fn group(&self) -> Result<Gid> {
+        Ok(Gid::new(self.attr()?.gid))
+    }
+
+    fn set_group(&self, gid: Gid) -> Result<()> {
+        let mut setattr_in = SetattrIn::new_zeroed();
+        setattr_in.valid = SetattrValid::GID.bits();
+        setattr_in.gid = gid.into();
+        self.setattr(setattr_in)
+    }
+
+    fn atime(&self) -> Duration {
+        let attr = self.attr_or_cached();
+        Duration::new(attr.atime, attr.atimensec)
+    }
+
+    fn set_atime(&self, time: Duration) {
+        let _ = self.set_time(SetattrValid::ATIME, time);
+    }
+
+    fn mtime(&self) -> Duration {
+        let attr = self.attr_or_cached();
+        Duration::new(attr.mtime, attr.mtimensec)
+    }
+
+    fn set_mtime(&self, time: Duration) {
+        let _ = self.set_time(SetattrValid::MTIME, time);
+    }
+
+    fn ctime(&self) -> Duration {
+        let attr = self.attr_or_cached();
+        Duration::new(attr.ctime, attr.ctimensec)
+    }
+
+    fn set_ctime(&self, time: Duration) {
+        // The daemons of old versions do not understand the ctime in `SETATTR`.
+        if self
+            .conn
+            .wait_initialized()
+            .is_ok_and(|init| init.minor >= 23)
+        {
+            let _ = self.set_time(SetattrValid::CTIME, time);
+        }
+    }
+
+    fn create(&self, name: &str, type_: InodeType, mode: InodeMode) -> Result<Arc<dyn Inode>> {
+        self.check_dir()?;
+
+        match type_ {
+            InodeType::Dir => {
+                let mkdir_in = MkdirIn {
+                    mode: mode.bits() as u32,
+                    umask: 0,
+                };
+                let reply =
+                    self.request(Opcode::Mkdir, &[mkdir_in.as_bytes(), name.as_bytes(), &[0]])?;
+                Ok(self.new_entry(&reply)?)
+            }
+            InodeType::SymLink => {
+                let mut attr = Attr::new_zeroed();
+                attr.mode = InodeType::SymLink as u32 | mode.bits() as u32;
+                attr.nlink = 1;
+                let inode = Self::new(self.fs.clone(), self.conn.clone(), 0, attr, Duration::ZERO);
+                *inode.pending_symlink.lock() = Some((self.nodeid, name.to_string()));
+                Ok(inode)
+            }
+            _ => self.mknod_raw(name, type_ as u32 | mode.bits() as u32, 0),
+        }
+    }
+
+    fn mknod(&self, name: &str, mode: InodeMode, type_: MknodType) -> Result<Arc<dyn Inode>> {
+        let (inode_type, rdev) = match type_ {
+            MknodType::NamedPipe => (InodeType::NamedPipe, 0),
+            MknodType::CharDevice(rdev) => (InodeType::CharDevice, rdev),
+            MknodType::BlockDevice(rdev) => (InodeType::BlockDevice, rdev),
+        };
+        self.mknod_raw(name, inode_type as u32 | mode.bits() as u32, rdev as u32)
+    }
+
+    fn open(
+        &self,
+        access_mode: AccessMode,
+        status_flags: StatusFlags,
+    ) -> Option<Result<Box<dyn FileIo>>> {
+        if self.type_ != InodeType::File {
+            return None;
+        }
+
+        let flags = access_mode as u32 | status_flags.bits();
+        let open_out = match self.open_handle(flags) {
+            Ok(open_out) => open_out,
+            Err(err) => return Some(Err(err)),
+        };
+        Some(Ok(Box::new(FuseFile {
+            inode: self.this.upgrade().unwrap(),
+            fh: open_out.fh,
+            flags,
+            open_flags: OpenOutFlags::from_bits_truncate(open_out.open_flags),
+        })))
+    }
+
+    fn readdir_at(&self, offset: usize, visitor: &mut dyn DirentVisitor) -> Result<usize> {
+        self.check_dir()?;
+
+        self.with_handle(AccessMode::O_RDONLY as u32, |fh| {
+            let mut current_offset = offset;
+            'read: loop {
+                let read_in = ReadIn {
+                    fh,
+                    offset: current_offset as u64,
+                    size: PAGE_SIZE as u32,
+                    read_flags: 0,
+                    lock_owner: 0,
+                    flags: 0,
+                    padding: 0,
+                };
+                let reply = self.request(Opcode::Readdir, &[read_in.as_bytes()])?;
+                if reply.is_empty() {
+                    break;
+                }
+
+                let mut pos = 0;
+                while pos < reply.len() {
+                    let dirent = parse_reply::<Dirent>(&reply[pos..])?;
+                    let name_start = pos + size_of::<Dirent>();
+                    let name = reply
+                        .get(name_start..name_start + dirent.namelen as usize)
+                        .and_then(|name| core::str::from_utf8(name).ok())
+                        .ok_or_else(|| Error::with_message(Errno::EIO, "invalid FUSE dirent"))?;
+                    // The offsets are opaque, but we need them to increase to report the
+                    // progress of reading the directory.
+                    let next_offset = dirent.off as usize;
+                    if next_offset <= current_offset {
+                        return_errno_with_message!(Errno::EIO, "the FUSE dirents go backwards");
+                    }
+
+                    let type_ = InodeType::from_raw_mode((dirent.type_ << 12) as u16)
+                        .unwrap_or(InodeType::Unknown);
+                    if let Err(err) = visitor.visit(name, dirent.ino, type_, next_offset) {
+                        if current_offset == offset {
+                            return Err(err);
+                        }
+                        break 'read;
+                    }
+                    current_offset = next_offset;
+                    pos = (name_start + dirent.namelen as usize).next_multiple_of(8);
+                }
+            }
+
+            Ok(current_offset - offset)
+        })
+    }
+
+    fn link(&self, old: &Arc<dyn Inode>, name: &str) -> Result<()> {
+        self.check_dir()?;
+        let old = old
+            .downcast_ref::<FuseInode>()
+            .filter(|old| Weak::ptr_eq(&old.fs, &self.fs))
+            .ok_or_else(|| Error::with_message(Errno::EXDEV, "not the same fs"))?;
+
+        let link_in = LinkIn {
+            oldnodeid: old.nodeid,
+        };
+        let reply = self.request(Opcode::Link, &[link_in.as_bytes(), name.as_bytes(), &[0]])?;
+        self.new_entry(&reply)?;
+        old.invalidate_attr();
+        Ok(())
+    }
+
+    fn unlink(&self, name: &str) -> Result<()> {
+        self.check_dir()?;
+        self.request(Opcode::Unlink, &[name.as_bytes(), &[0]])?;
+        Ok(())
+    }
+
+    fn rmdir(&self, name: &str) -> Result<()> {
+        self.check_dir()?;
+        self.request(Opcode::Rmdir, &[name.as_bytes(), &[0]])?;
+        Ok(())
+    }
+
+    fn lookup(&self, name: &str) -> Result<Arc<dyn Inode>> {
+        self.check_dir()?;
+        let reply = self.request(Opcode::Lookup, &[name.as_bytes(), &[0]])?;
+        Ok(self.new_entry(&reply)?)
+    }
+
+    fn rename(&self, old_name: &str, target: &Arc<dyn Inode>, new_name: &str) -> Result<()> {
+        self.check_dir()?;
+        let target = target
+            .downcast_ref::<FuseInode>()
+            .filter(|target| Weak::ptr_eq(&target.fs, &self.fs))
+            .ok_or_else(|| Error::with_message(Errno::EXDEV, "not the same fs"))?;
+        target.check_dir()?;
+
+        let rename_in = RenameIn {
+            newdir: target.nodeid,
+        };
+        self.request(
+            Opcode::Rename,
+            &[
+                rename_in.as_bytes(),
+                old_name.as_bytes(),
+                &[0],
+                new_name.as_bytes(),
+                &[0],
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn read_link(&self) -> Result<SymbolicLink> {
+        if self.type_ != InodeType::SymLink {
+            return_errno!(Errno::EINVAL);
+        }
+        let reply = self.request(Opcode::Readlink, &[])?;
+        let target = String::from_utf8(reply)
+            .map_err(|_| Error::with_message(Errno::EIO, "the link target is not UTF-8"))?;
+        Ok(SymbolicLink::Plain(target))
+    }
+
+    fn write_link(&self, target: &str) -> Result<()> {
+        let Some((parent, name)) = self.pending_symlink.lock().take() else {
+            return_errno_with_message!(Errno::EEXIST, "the symbolic link is already created");
+        };
+
+        let reply = self.conn.request(
+            Opcode::Symlink,
+            parent,
+            &[name.as_bytes(), &[0], target.as_bytes(), &[0]],
+        )?;
+        // The VFS does not cache the entries, so the created inode is looked up again
+        // when it is used.
+        let entry_out = parse_reply::<EntryOut>(&reply)?;
+        if entry_out.nodeid != 0 {
+            let forget_in = ForgetIn { nlookup: 1 };
+            let _ = self
+                .conn
+                .request(Opcode::Forget, entry_out.nodeid, &[forget_in.as_bytes()]);
+        }
+        Ok(())
+    }
+
+    fn sync_all(&self) -> Result<()> {
+        if self.type_ != InodeType::File {
+            return Ok(());
+        }
+        self.with_handle(AccessMode::O_RDONLY as u32, |fh| {
+            let fsync_in = FsyncIn {
+                fh,
+                fsync_flags: 0,
+                padding: 0,
+            };
+            self.request(Opcode::Fsync, &[fsync_in.as_bytes()])?;
+            Ok(())
+        })
+    }
+
+    fn sync_data(&self) -> Result<()> {
+        if self.type_ != InodeType::File {
+            return Ok(());
+        }
+        self.with_handle(AccessMode::O_RDONLY as u32, |fh| {
+            // The lowest bit asks the daemon to sync only the data.
+            let fsync_in = FsyncIn {
+                fh,
+                fsync_flags: 1,
+                padding: 0,
+            };
+            self.request(Opcode::Fsync, &[fsync_in.as_bytes()])?;
+            Ok(())
+        })
+    }
+
+    fn fallocate(&self, mode: FallocMode, offset: usize, len: usize) -> Result<()> {
+        let mode = match mode {
+            FallocMode::Allocate => 0,
+            FallocMode::AllocateKeepSize => FALLOC_FL_KEEP_SIZE,
+            FallocMode::AllocateUnshareRange => FALLOC_FL_UNSHARE_RANGE,
+            FallocMode::PunchHoleKeepSize => FALLOC_FL_PUNCH_HOLE | FALLOC_FL_KEEP_SIZE,
+            FallocMode::ZeroRange => FALLOC_FL_ZERO_RANGE,
+            FallocMode::ZeroRangeKeepSize => FALLOC_FL_ZERO_RANGE | FALLOC_FL_KEEP_SIZE,
+            FallocMode::CollapseRange => FALLOC_FL_COLLAPSE_RANGE,
+            FallocMode::InsertRange => FALLOC_FL_INSERT_RANGE,
+        };
+
+        self.with_handle(AccessMode::O_WRONLY as u32, |fh| {
+            let fallocate_in = FallocateIn {
+                fh,
+                offset: offset as u64,
+                length: len as u64,
+                mode,
+                padding: 0,
+            };
+            self.request(Opcode::Fallocate, &[fallocate_in.as_bytes()])?;
+            self.invalidate_attr();
+            Ok(())
+        })
+    }
+
+    fn fs(&self) -> Arc<dyn FileSystem> {
+        self.fs()
+    }
+
+    fn is_dentry_cacheable(&self) -> bool {
+        // The files can be changed by the daemon without notice.
+        false
+    }
+
+    fn extension(&self) -> &Extension {
+        &self.extension
+    }
+
+    fn check_permission(&self, perm: Permission) -> Result<()> {
+        let fs = self.fs();
+        let options = fs.options();
+
+        let credentials = match Task::current() {
+            Some(task) => match task.as_posix_thread() {
+                Some(thread) => thread.credentials(),
+                None => return Ok(()),
+            },
+            None => return Ok(()),
+        };
+        // Only the mounting user can access the files, unless `allow_other` is given.
+        if !options.allow_other
+            && (credentials.fsuid() != options.user_id || credentials.fsgid() != options.group_id)
+        {
+            return_errno_with_message!(Errno::EACCES, "the file is owned by another FUSE user");
+        }
+
+        // Without `default_permissions`, the permissions are checked by the daemon.
+        if !options.default_permissions {
+            return Ok(());
+        }
+        let metadata = self.metadata();
+        check_dac_permission(metadata.mode, metadata.uid, metadata.gid, None, perm)
+    }
+}
+
+/// Returns the monotonic time at which a validity period from now ends.
+fn expiry(secs: u64, nsecs: u32) -> Duration {
+    let valid = Duration::new(secs, nsecs.min(999_999_999));
+    MonotonicCoarseClock::get()
+        .read_time()
+        .saturating_add(valid)
+}
+
+/// An opened regular file of a FUSE filesystem.
+struct FuseFile {
+    inode: Arc<FuseInode>,
+    fh: u64,
+    /// The flags with which the file is opened.
+    flags: u32,
+    open_flags: OpenOutFlags,
+}
+
+impl Drop for FuseFile {
+    fn drop(&mut self) {
+        let flush_in = FlushIn {
+            fh: self.fh,
+            unused: 0,
+            padding: 0,
+            lock_owner: 0,
+        };
+        let _ = self.inode.request(Opcode::Flush, &[flush_in.as_bytes()]);
+        self.inode.release_handle(self.fh, self.flags);
+    }
+}
+
+impl Pollable for FuseFile {
+    fn poll(&self, mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
+        let events = IoEvents::IN | IoEvents::OUT;
+        events & mask
+    }
+}
+
+impl InodeIo for FuseFile {
+    fn read_at(
+        &self,
+        offset: usize,
+        writer: &mut VmWriter,
+        _status_flags: StatusFlags,
+    ) -> Result<usize> {
+        self.inode.read_handle(self.fh, offset, writer)
+    }
+
+    fn write_at(
+        &self,
+        offset: usize,
+        reader: &mut VmReader,
+        status_flags: StatusFlags,
+    ) -> Result<usize> {
+        let offset = if status_flags.contains(StatusFlags::O_APPEND) {
+            self.inode.attr()?.size as usize
+        } else {
+            offset
+        };
+        self.inode.write_handle(self.fh, offset, reader)
+    }
+}
+
+impl FileIo for FuseFile {
+    fn check_seekable(&self) -> Result<()> {
+        if self.open_flags.contains(OpenOutFlags::NONSEEKABLE) {
+            return_errno_with_message!(Errno::ESPIPE, "the FUSE file is not seekable");
+        }
+        Ok(())
+    }
+
+    fn is_offset_aware(&self) -> bool {
+        true
+    }
+}
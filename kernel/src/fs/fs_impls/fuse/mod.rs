@@ -0,0 +1,44 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! FUSE, i.e., the filesystems in userspace.
+//!
+//! A FUSE daemon opens `/dev/fuse` and mounts a `fuse` filesystem with the file
+//! descriptor in the mount options (`fd`, `rootmode`, `user_id` and `group_id`).
+//! Then the operations of the filesystem are sent to the daemon as requests, which
+//! are read from the device file, and the replies are written back to it.
+//!
+//! A `virtiofs` filesystem is also served by FUSE, whose requests are sent to a virtio
+//! filesystem device. The host shares a directory through the device, which is mounted
+//! with the tag of the device as the source, e.g., `mount -t virtiofs hostshare /mnt`.
+//!
+//! The features of this version of FUSE are as follows:
+//! 1. The requests of lookups, attributes, directories, links, opened files and
+//!    `statfs`, which are enough for the common daemons such as sshfs.
+//! 2. The requests are interruptible. A signal removes a request that has not been
+//!    read, or sends `INTERRUPT` to the daemon.
+//! 3. The attributes are cached until they expire as the daemon specifies.
+//! 4. The mount options `default_permissions`, `allow_other`, `max_read` and `blksize`.
+//!
+//! # Limitation
+//!
+//! Here we summarizes the features that need to be implemented in the future.
+//! 1. Supports the page cache, including `mmap` and the writeback cache.
+//! 2. Supports the notifications of the daemon, `READDIRPLUS` and `CREATE`.
+//! 3. Supports the directory offsets that are not increasing. The handles of the
+//!    directories are opened for each `getdents`.
+//! 4. Supports the extended attributes, the file locks and `ioctl`.
+
+pub use dev::FuseDevFile;
+
+use crate::fs::fuse::fs::{FuseType, VirtioFsType};
+
+mod abi;
+mod conn;
+mod dev;
+mod fs;
+mod inode;
+
+pub(super) fn init() {
+    crate::fs::vfs::registry::register(&FuseType).unwrap();
+    crate::fs::vfs::registry::register(&VirtioFsType).unwrap();
+}
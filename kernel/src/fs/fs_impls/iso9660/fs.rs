@@ -0,0 +1,264 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_block::{BlockDevice, SECTOR_SIZE};
+use ostd::mm::VmIo;
+
+use super::{
+    inode::{EntryInfo, IsoInode},
+    record::{DirRecord, read_le_u16, read_le_u32},
+    rock_ridge::RockRidge,
+};
+use crate::{
+    fs::vfs::{
+        file_system::{FileSystem, FsEventSubscriberStats, FsFlags, SuperBlock},
+        inode::Inode,
+        registry::{FsProperties, FsType},
+    },
+    prelude::*,
+};
+
+/// The magic number of ISO9660 in `statfs`.
+const ISOFS_MAGIC: u64 = 0x9660;
+
+/// The maximum length of the names, which is reached by Rock Ridge.
+const MAX_NAME_LEN: usize = 255;
+
+/// The size of the volume descriptors, which are in units of 2048 bytes regardless of
+/// the logical block size.
+const VOLUME_DESCRIPTOR_SIZE: usize = 2048;
+/// The index of the first volume descriptor, after the system area.
+const FIRST_VOLUME_DESCRIPTOR: usize = 16;
+/// The maximum number of volume descriptors that are scanned for the terminator.
+const MAX_VOLUME_DESCRIPTORS: usize = 64;
+
+const PRIMARY_DESCRIPTOR: u8 = 1;
+const SUPPLEMENTARY_DESCRIPTOR: u8 = 2;
+const TERMINATOR_DESCRIPTOR: u8 = 255;
+
+/// The escape sequences of the supplementary volume descriptors that indicate Joliet,
+/// i.e., UCS-2 level 1, 2 and 3.
+const JOLIET_ESCAPES: [&[u8]; 3] = [b"%/@", b"%/C", b"%/E"];
+
+/// The way that the names of files are recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum NameFormat {
+    /// The POSIX names of the Rock Ridge `NM` entries, whose system use areas start
+    /// after `skip` bytes.
+    RockRidge { skip: usize },
+    /// The UCS-2 names of the Joliet directory hierarchy.
+    Joliet,
+    /// The uppercase 8.3 names of the primary directory hierarchy.
+    Plain,
+}
+
+/// A read-only ISO9660 filesystem with the Rock Ridge and Joliet extensions.
+pub struct Iso9660Fs {
+    block_device: Arc<dyn BlockDevice>,
+    /// The logical block size, in which the extents are located.
+    block_size: usize,
+    /// The number of logical blocks of the volume.
+    volume_blocks: u64,
+    device_size: u64,
+    name_format: NameFormat,
+    /// The loaded inodes, indexed by their inode numbers.
+    inodes: Mutex<BTreeMap<u64, Arc<IsoInode>>>,
+    root_ino: u64,
+    fs_event_subscriber_stats: FsEventSubscriberStats,
+}
+
+impl Iso9660Fs {
+    /// Opens an ISO9660 filesystem on the block device.
+    pub(super) fn open(block_device: Arc<dyn BlockDevice>) -> Result<Arc<Self>> {
+        let device_size = (block_device.metadata().nr_sectors * SECTOR_SIZE) as u64;
+
+        let mut primary = None;
+        let mut joliet = None;
+        for idx in FIRST_VOLUME_DESCRIPTOR..FIRST_VOLUME_DESCRIPTOR + MAX_VOLUME_DESCRIPTORS {
+            let offset = idx * VOLUME_DESCRIPTOR_SIZE;
+            if (offset + VOLUME_DESCRIPTOR_SIZE) as u64 > device_size {
+                break;
+            }
+            let mut descriptor = vec![0u8; VOLUME_DESCRIPTOR_SIZE];
+            block_device.read_bytes(offset, &mut descriptor)?;
+            if &descriptor[1..6] != b"CD001" {
+                break;
+            }
+
+            match descriptor[0] {
+                PRIMARY_DESCRIPTOR if primary.is_none() => primary = Some(descriptor),
+                SUPPLEMENTARY_DESCRIPTOR
+                    if joliet.is_none()
+                        && JOLIET_ESCAPES
+                            .iter()
+                            .any(|escape| descriptor[88..91] == **escape) =>
+                {
+                    joliet = Some(descriptor)
+                }
+                TERMINATOR_DESCRIPTOR => break,
+                _ => {}
+            }
+        }
+        let Some(primary) = primary else {
+            return_errno_with_message!(Errno::EINVAL, "no ISO9660 primary volume descriptor");
+        };
+
+        let block_size = read_le_u16(&primary, 128) as usize;
+        if !block_size.is_power_of_two() || !(512..=VOLUME_DESCRIPTOR_SIZE).contains(&block_size) {
+            return_errno_with_message!(Errno::EINVAL, "invalid ISO9660 logical block size");
+        }
+
+        let mut fs = Self {
+            block_device,
+            block_size,
+            volume_blocks: read_le_u32(&primary, 80) as u64,
+            device_size,
+            name_format: NameFormat::Plain,
+            inodes: Mutex::new(BTreeMap::new()),
+            root_ino: 0,
+            fs_event_subscriber_stats: FsEventSubscriberStats::new(),
+        };
+
+        // Rock Ridge is preferred to Joliet, as it gives the POSIX attributes besides
+        // the names. It is detected by the `SP` entry of the `.` record of the root.
+        let primary_root = fs.read_root_dot(&primary)?;
+        let rock_ridge = {
+            let record = DirRecord::parse(&primary_root)?;
+            RockRidge::detect(record.system_use)
+        };
+        let root_dot = match (rock_ridge, joliet) {
+            (Some(skip), _) => {
+                fs.name_format = NameFormat::RockRidge { skip };
+                primary_root
+            }
+            (None, Some(joliet)) => {
+                fs.name_format = NameFormat::Joliet;
+                fs.read_root_dot(&joliet)?
+            }
+            (None, None) => primary_root,
+        };
+
+        let root_info = EntryInfo::from_record(&fs, &DirRecord::parse(&root_dot)?)?;
+        fs.root_ino = root_info.dir_ino();
+
+        let fs = Arc::new(fs);
+        // The parent of the root directory is itself.
+        IsoInode::load(&fs, fs.root_ino, &root_info, fs.root_ino)?;
+
+        Ok(fs)
+    }
+
+    /// Reads the `.` record of the root directory of a volume descriptor.
+    fn read_root_dot(&self, descriptor: &[u8]) -> Result<Vec<u8>> {
+        let root = DirRecord::parse(&descriptor[156..190])?;
+        let mut block = vec![0u8; self.block_size];
+        self.read_bytes(root.extent as u64 * self.block_size as u64, &mut block)?;
+        Ok(block)
+    }
+
+    pub(super) fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    pub(super) fn name_format(&self) -> NameFormat {
+        self.name_format
+    }
+
+    pub(super) fn block_device(&self) -> &dyn BlockDevice {
+        self.block_device.as_ref()
+    }
+
+    pub(super) fn cached_inode(&self, ino: u64) -> Option<Arc<IsoInode>> {
+        self.inodes.lock().get(&ino).cloned()
+    }
+
+    /// Caches a loaded inode, and returns the one that wins the race of loading.
+    pub(super) fn cache_inode(&self, inode: Arc<IsoInode>) -> Arc<IsoInode> {
+        self.inodes
+            .lock()
+            .entry(inode.ino())
+            .or_insert(inode)
+            .clone()
+    }
+
+    /// Reads `buf.len()` bytes starting from the byte `offset` of the device.
+    pub(super) fn read_bytes(&self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        let end = offset
+            .checked_add(buf.len() as u64)
+            .filter(|end| *end <= self.device_size)
+            .ok_or_else(|| Error::with_message(Errno::EIO, "read beyond the ISO9660 image"))?;
+        if buf.is_empty() {
+            return Ok(());
+        }
+
+        // The device can only be read in sectors.
+        let sector_size = SECTOR_SIZE as u64;
+        let aligned_start = offset / sector_size * sector_size;
+        let aligned_end = end.div_ceil(sector_size) * sector_size;
+        let mut sectors = vec![0u8; (aligned_end - aligned_start) as usize];
+        self.block_device
+            .read_bytes(aligned_start as usize, &mut sectors)?;
+
+        let start = (offset - aligned_start) as usize;
+        buf.copy_from_slice(&sectors[start..start + buf.len()]);
+        Ok(())
+    }
+}
+
+impl FileSystem for Iso9660Fs {
+    fn name(&self) -> &'static str {
+        "iso9660"
+    }
+
+    fn sync(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn root_inode(&self) -> Arc<dyn Inode> {
+        self.cached_inode(self.root_ino).unwrap()
+    }
+
+    fn sb(&self) -> SuperBlock {
+        let mut sb = SuperBlock::new(
+            ISOFS_MAGIC,
+            self.block_size,
+            MAX_NAME_LEN,
+            self.block_device.id(),
+        );
+        sb.blocks = self.volume_blocks as usize;
+        sb.bfree = 0;
+        sb.bavail = 0;
+        sb.ffree = 0;
+        sb
+    }
+
+    fn fs_event_subscriber_stats(&self) -> &FsEventSubscriberStats {
+        &self.fs_event_subscriber_stats
+    }
+}
+
+pub(super) struct Iso9660Type;
+
+impl FsType for Iso9660Type {
+    fn name(&self) -> &'static str {
+        "iso9660"
+    }
+
+    fn properties(&self) -> FsProperties {
+        FsProperties::NEED_DISK
+    }
+
+    fn create(
+        &self,
+        _flags: FsFlags,
+        _source: Option<&str>,
+        _args: Option<CString>,
+        disk: Option<Arc<dyn BlockDevice>>,
+    ) -> Result<Arc<dyn FileSystem>> {
+        let fs = Iso9660Fs::open(disk.unwrap())?;
+        Ok(fs)
+    }
+
+    fn sysnode(&self) -> Option<Arc<dyn aster_systree::SysNode>> {
+        None
+    }
+}
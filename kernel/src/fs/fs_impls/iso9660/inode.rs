@@ -0,0 +1,556 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use aster_block::{SECTOR_SIZE, bio::BioWaiter};
+use device_id::{DeviceId, encode_device_numbers};
+use ostd::mm::{VmIo, io::util::HasVmReaderWriter};
+
+use super::{
+    fs::{Iso9660Fs, NameFormat},
+    record::{DirRecord, RecordFlags, joliet_name, plain_name},
+    rock_ridge::RockRidge,
+};
+use crate::{
+    fs::{
+        file::{InodeMode, InodeType, StatusFlags},
+        utils::DirentVisitor,
+        vfs::{
+            file_system::FileSystem,
+            inode::{Extension, Inode, InodeIo, Metadata, MknodType, SymbolicLink},
+            page_cache::{CachePage, PageCache, PageCacheBackend},
+        },
+    },
+    prelude::*,
+    process::{Gid, Uid},
+    vm::vmo::Vmo,
+};
+
+/// The maximum size of a directory, which bounds the memory used to load it.
+const MAX_DIR_SIZE: u64 = 16 * 1024 * 1024;
+
+/// The permissions of the directories without Rock Ridge attributes.
+const DEFAULT_DIR_MODE: u16 = 0o555;
+/// The permissions of the files without Rock Ridge attributes.
+const DEFAULT_FILE_MODE: u16 = 0o444;
+
+/// The information of a file that is given by its directory records.
+#[derive(Debug, Clone)]
+pub(super) struct EntryInfo {
+    /// The byte offsets and the lengths of the extents.
+    extents: Vec<(u64, u64)>,
+    size: u64,
+    is_dir: bool,
+    time: Duration,
+    rock_ridge: Option<RockRidge>,
+}
+
+impl EntryInfo {
+    pub(super) fn from_record(fs: &Iso9660Fs, record: &DirRecord) -> Result<Self> {
+        let rock_ridge = match fs.name_format() {
+            NameFormat::RockRidge { skip } => Some(RockRidge::parse(
+                fs,
+                record.system_use.get(skip..).unwrap_or(&[]),
+            )?),
+            NameFormat::Joliet | NameFormat::Plain => None,
+        };
+
+        Ok(Self {
+            extents: vec![(
+                record.extent as u64 * fs.block_size() as u64,
+                record.data_len as u64,
+            )],
+            size: record.data_len as u64,
+            is_dir: record.flags.contains(RecordFlags::DIRECTORY),
+            time: record.time,
+            rock_ridge,
+        })
+    }
+
+    /// Appends the extent of a following record of a multi-extent file.
+    fn push_extent(&mut self, fs: &Iso9660Fs, record: &DirRecord) {
+        self.extents.push((
+            record.extent as u64 * fs.block_size() as u64,
+            record.data_len as u64,
+        ));
+        self.size += record.data_len as u64;
+    }
+
+    /// Returns the inode number of a directory, which is the location of its extent.
+    ///
+    /// The records of a directory are in itself and its parent, so the location of the
+    /// extent is the only stable identity of it.
+    pub(super) fn dir_ino(&self) -> u64 {
+        self.extents[0].0
+    }
+
+    fn type_(&self) -> InodeType {
+        if self.is_dir {
+            return InodeType::Dir;
+        }
+        self.rock_ridge
+            .as_ref()
+            .and_then(|rock_ridge| rock_ridge.mode)
+            .and_then(|mode| InodeType::from_raw_mode(mode).ok())
+            .filter(|type_| *type_ != InodeType::Dir)
+            .unwrap_or(InodeType::File)
+    }
+}
+
+/// An entry of a directory.
+struct DirEntry {
+    name: String,
+    ino: u64,
+    type_: InodeType,
+    info: EntryInfo,
+}
+
+impl DirEntry {
+    /// Creates an entry from the records of a file, or returns `None` if it is hidden.
+    fn new(
+        fs: &Iso9660Fs,
+        name: String,
+        record_pos: u64,
+        mut info: EntryInfo,
+    ) -> Result<Option<Self>> {
+        if let Some(rock_ridge) = info.rock_ridge.as_ref() {
+            if rock_ridge.relocated {
+                return Ok(None);
+            }
+
+            // A deep directory is relocated by Rock Ridge, and its attributes are in
+            // the `.` record of the relocated one.
+            if let Some(child_link) = rock_ridge.child_link {
+                let mut block = vec![0u8; fs.block_size()];
+                fs.read_bytes(child_link as u64 * fs.block_size() as u64, &mut block)?;
+                info = EntryInfo::from_record(fs, &DirRecord::parse(&block)?)?;
+                info.is_dir = true;
+            }
+        }
+
+        // The record position identifies a file, as files may share empty extents.
+        let ino = if info.is_dir {
+            info.dir_ino()
+        } else {
+            record_pos
+        };
+        Ok(Some(Self {
+            name,
+            ino,
+            type_: info.type_(),
+            info,
+        }))
+    }
+}
+
+/// An inode of ISO9660.
+pub(super) struct IsoInode {
+    ino: u64,
+    type_: InodeType,
+    mode: u16,
+    uid: Uid,
+    gid: Gid,
+    nlink: u32,
+    size: usize,
+    atime: Duration,
+    mtime: Duration,
+    ctime: Duration,
+    parent_ino: u64,
+    data: InodeData,
+    /// The page cache of a regular file.
+    page_cache: Option<PageCache>,
+    fs: Weak<Iso9660Fs>,
+    extension: Extension,
+}
+
+enum InodeData {
+    Dir(Vec<DirEntry>),
+    /// The byte offsets and the lengths of the extents.
+    File(Vec<(u64, u64)>),
+    Symlink(String),
+    Device(u64),
+    Ipc,
+}
+
+impl IsoInode {
+    /// Loads the inode of a file, or returns the loaded one.
+    pub(super) fn load(
+        fs: &Arc<Iso9660Fs>,
+        ino: u64,
+        info: &EntryInfo,
+        parent_ino: u64,
+    ) -> Result<Arc<Self>> {
+        if let Some(inode) = fs.cached_inode(ino) {
+            return Ok(inode);
+        }
+
+        let type_ = info.type_();
+        let rock_ridge = info.rock_ridge.clone().unwrap_or_default();
+        let data = match type_ {
+            InodeType::Dir => InodeData::Dir(read_dir_entries(fs, info)?),
+            InodeType::File => InodeData::File(info.extents.clone()),
+            InodeType::SymLink => InodeData::Symlink(rock_ridge.symlink.unwrap_or_default()),
+            InodeType::BlockDevice | InodeType::CharDevice => {
+                let (major, minor) = rock_ridge.device.unwrap_or_default();
+                InodeData::Device(encode_device_numbers(major, minor))
+            }
+            _ => InodeData::Ipc,
+        };
+        let size = match &data {
+            InodeData::Dir(_) | InodeData::File(_) => info.size as usize,
+            InodeData::Symlink(target) => target.len(),
+            InodeData::Device(_) | InodeData::Ipc => 0,
+        };
+        let (default_mode, default_nlink) = if type_ == InodeType::Dir {
+            (DEFAULT_DIR_MODE, 2)
+        } else {
+            (DEFAULT_FILE_MODE, 1)
+        };
+
+        let inode = Arc::new_cyclic(|weak_self: &Weak<Self>| Self {
+            ino,
+            type_,
+            mode: rock_ridge.mode.map_or(default_mode, |mode| mode & 0o7777),
+            uid: Uid::new(rock_ridge.uid.unwrap_or(0)),
+            gid: Gid::new(rock_ridge.gid.unwrap_or(0)),
+            nlink: rock_ridge.nlink.unwrap_or(default_nlink),
+            size,
+            atime: rock_ridge.atime.unwrap_or(info.time),
+            mtime: rock_ridge.mtime.unwrap_or(info.time),
+            ctime: rock_ridge.ctime.unwrap_or(info.time),
+            parent_ino,
+            data,
+            page_cache: (type_ == InodeType::File)
+                .then(|| PageCache::with_capacity(size, weak_self.clone() as _).unwrap()),
+            fs: Arc::downgrade(fs),
+            extension: Extension::new(),
+        });
+        Ok(fs.cache_inode(inode))
+    }
+
+    fn fs(&self) -> Arc<Iso9660Fs> {
+        self.fs.upgrade().unwrap()
+    }
+
+    fn dir_entries(&self) -> Result<&[DirEntry]> {
+        match &self.data {
+            InodeData::Dir(entries) => Ok(entries),
+            _ => return_errno!(Errno::ENOTDIR),
+        }
+    }
+
+    /// Returns an error for the modifications of a directory.
+    fn modify_dir(&self) -> Result<()> {
+        self.dir_entries()?;
+        return_errno_with_message!(Errno::EROFS, "iso9660 is read-only")
+    }
+
+    fn read_page(&self, idx: usize, frame: &CachePage) -> Result<()> {
+        let InodeData::File(extents) = &self.data else {
+            return_errno!(Errno::EINVAL);
+        };
+        let start = idx * PAGE_SIZE;
+        if start >= self.size {
+            return_errno_with_message!(Errno::EINVAL, "the page is beyond the file");
+        }
+        let end = self.size.min(start + PAGE_SIZE);
+
+        let fs = self.fs();
+        let mut buf = vec![0u8; PAGE_SIZE];
+        let mut extent_start = 0;
+        for &(offset, len) in extents {
+            let extent_end = extent_start + len as usize;
+            let copy_start = start.max(extent_start);
+            let copy_end = end.min(extent_end);
+            if copy_start < copy_end {
+                fs.read_bytes(
+                    offset + (copy_start - extent_start) as u64,
+                    &mut buf[copy_start - start..copy_end - start],
+                )?;
+            }
+            if extent_end >= end {
+                break;
+            }
+            extent_start = extent_end;
+        }
+        frame.writer().write(&mut VmReader::from(buf.as_slice()));
+        Ok(())
+    }
+
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let Some(page_cache) = self.page_cache.as_ref() else {
+            return_errno!(Errno::EISDIR);
+        };
+
+        let start = self.size.min(offset);
+        let end = self.size.min(offset + writer.avail());
+        page_cache.pages().read(start, writer)?;
+        Ok(end - start)
+    }
+}
+
+/// Reads the entries of a directory, merging the records of multi-extent files.
+fn read_dir_entries(fs: &Iso9660Fs, info: &EntryInfo) -> Result<Vec<DirEntry>> {
+    if info.size > MAX_DIR_SIZE {
+        return_errno_with_message!(Errno::EIO, "the directory is too large");
+    }
+
+    let block_size = fs.block_size();
+    let mut entries = Vec::new();
+    let mut pending: Option<(String, u64, EntryInfo)> = None;
+    for &(extent_offset, extent_len) in info.extents.iter() {
+        let mut buf = vec![0u8; extent_len as usize];
+        fs.read_bytes(extent_offset, &mut buf)?;
+
+        let mut pos = 0;
+        while pos < buf.len() {
+            // The records never cross blocks, and the rest of a block is zero-padded.
+            if buf[pos] == 0 {
+                pos = (pos / block_size + 1) * block_size;
+                continue;
+            }
+            let record = DirRecord::parse(&buf[pos..])?;
+            let record_pos = extent_offset + pos as u64;
+            pos += buf[pos] as usize;
+            if record.is_dot_or_dotdot() || record.flags.contains(RecordFlags::ASSOCIATED) {
+                continue;
+            }
+
+            if let Some((_, _, pending_info)) = pending.as_mut() {
+                pending_info.push_extent(fs, &record);
+            } else {
+                let info = EntryInfo::from_record(fs, &record)?;
+                let name = match fs.name_format() {
+                    NameFormat::RockRidge { .. } => info
+                        .rock_ridge
+                        .as_ref()
+                        .and_then(|rock_ridge| rock_ridge.name.clone())
+                        .unwrap_or_else(|| plain_name(record.name)),
+                    NameFormat::Joliet => joliet_name(record.name),
+                    NameFormat::Plain => plain_name(record.name),
+                };
+                pending = Some((name, record_pos, info));
+            }
+            if record.flags.contains(RecordFlags::MULTI_EXTENT) {
+                continue;
+            }
+
+            let (name, record_pos, info) = pending.take().unwrap();
+            if let Some(entry) = DirEntry::new(fs, name, record_pos, info)? {
+                entries.push(entry);
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+impl PageCacheBackend for IsoInode {
+    fn read_page_async(&self, idx: usize, frame: &CachePage) -> Result<BioWaiter> {
+        self.read_page(idx, frame)?;
+        Ok(BioWaiter::new())
+    }
+
+    fn write_page_async(&self, _idx: usize, _frame: &CachePage) -> Result<BioWaiter> {
+        return_errno_with_message!(Errno::EROFS, "iso9660 is read-only")
+    }
+
+    fn npages(&self) -> usize {
+        self.size.div_ceil(PAGE_SIZE)
+    }
+}
+
+impl InodeIo for IsoInode {
+    fn read_at(
+        &self,
+        offset: usize,
+        writer: &mut VmWriter,
+        _status_flags: StatusFlags,
+    ) -> Result<usize> {
+        self.read_at(offset, writer)
+    }
+
+    fn write_at(
+        &self,
+        _offset: usize,
+        _reader: &mut VmReader,
+        _status_flags: StatusFlags,
+    ) -> Result<usize> {
+        return_errno_with_message!(Errno::EROFS, "iso9660 is read-only")
+    }
+}
+
+impl Inode for IsoInode {
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn resize(&self, _new_size: usize) -> Result<()> {
+        return_errno_with_message!(Errno::EROFS, "iso9660 is read-only")
+    }
+
+    fn metadata(&self) -> Metadata {
+        let fs = self.fs();
+        Metadata {
+            ino: self.ino,
+            size: self.size,
+            optimal_block_size: fs.block_size(),
+            nr_sectors_allocated: self.size.div_ceil(SECTOR_SIZE),
+            last_access_at: self.atime,
+            last_modify_at: self.mtime,
+            last_meta_change_at: self.ctime,
+            type_: self.type_,
+            mode: InodeMode::from_bits_truncate(self.mode),
+            nr_hard_links: self.nlink as usize,
+            uid: self.uid,
+            gid: self.gid,
+            container_dev_id: fs.block_device().id(),
+            self_dev_id: match self.data {
+                InodeData::Device(device) => DeviceId::from_encoded_u64(device),
+                _ => None,
+            },
+        }
+    }
+
+    fn ino(&self) -> u64 {
+        self.ino
+    }
+
+    fn type_(&self) -> InodeType {
+        self.type_
+    }
+
+    fn mode(&self) -> Result<InodeMode> {
+        Ok(InodeMode::from_bits_truncate(self.mode))
+    }
+
+    fn set_mode(&self, _mode: InodeMode) -> Result<()> {
+        return_errno_with_message!(Errno::EROFS, "iso9660 is read-only")
+    }
+
+    fn owner(&self) -> Result<Uid> {
+        Ok(self.uid)
+    }
+
+    fn set_owner(&self, _uid: Uid) -> Result<()> {
+        return_errno_with_message!(Errno::EROFS, "iso9660 is read-only")
+    }
+
+    fn group(&self) -> Result<Gid> {
+        Ok(self.gid)
+    }
+
+    fn set_group(&self, _gid: Gid) -> Result<()> {
+        return_errno_with_message!(Errno::EROFS, "iso9660 is read-only")
+    }
+
+    fn atime(&self) -> Duration {
+        self.atime
+    }
+
+    fn set_atime(&self, _time: Duration) {}
+
+    fn mtime(&self) -> Duration {
+        self.mtime
+    }
+
+    fn set_mtime(&self, _time: Duration) {}
+
+    fn ctime(&self) -> Duration {
+        self.ctime
+    }
+
+    fn set_ctime(&self, _time: Duration) {}
+
+    fn page_cache(&self) -> Option<Arc<Vmo>> {
+        self.page_cache
+            .as_ref()
+            .map(|page_cache| page_cache.pages().clone())
+    }
+
+    fn create(&self, _name: &str, _type_: InodeType, _mode: InodeMode) -> Result<Arc<dyn Inode>> {
+        self.modify_dir()?;
+        unreachable!()
+    }
+
+    fn mknod(&self, _name: &str, _mode: InodeMode, _type_: MknodType) -> Result<Arc<dyn Inode>> {
+        self.modify_dir()?;
+        unreachable!()
+    }
+
+    fn readdir_at(&self, offset: usize, visitor: &mut dyn DirentVisitor) -> Result<usize> {
+        let entries = self.dir_entries()?;
+
+        // The offsets 0 and 1 are `.` and `..`, and the offset `n + 2` is the entry `n`.
+        let try_readdir = |offset: &mut usize, visitor: &mut dyn DirentVisitor| -> Result<()> {
+            if *offset == 0 {
+                visitor.visit(".", self.ino, InodeType::Dir, 1)?;
+                *offset = 1;
+            }
+            if *offset == 1 {
+                visitor.visit("..", self.parent_ino, InodeType::Dir, 2)?;
+                *offset = 2;
+            }
+            for (idx, entry) in entries.iter().enumerate().skip(*offset - 2) {
+                visitor.visit(&entry.name, entry.ino, entry.type_, idx + 3)?;
+                *offset = idx + 3;
+            }
+            Ok(())
+        };
+
+        let mut iterate_offset = offset;
+        match try_readdir(&mut iterate_offset, visitor) {
+            Err(e) if iterate_offset == offset => Err(e),
+            _ => Ok(iterate_offset - offset),
+        }
+    }
+
+    fn link(&self, _old: &Arc<dyn Inode>, _name: &str) -> Result<()> {
+        self.modify_dir()
+    }
+
+    fn unlink(&self, _name: &str) -> Result<()> {
+        self.modify_dir()
+    }
+
+    fn rmdir(&self, _name: &str) -> Result<()> {
+        self.modify_dir()
+    }
+
+    fn lookup(&self, name: &str) -> Result<Arc<dyn Inode>> {
+        let entry = self
+            .dir_entries()?
+            .iter()
+            .find(|entry| entry.name == name)
+            .ok_or_else(|| Error::new(Errno::ENOENT))?;
+        Ok(IsoInode::load(
+            &self.fs(),
+            entry.ino,
+            &entry.info,
+            self.ino,
+        )?)
+    }
+
+    fn rename(&self, _old_name: &str, _target: &Arc<dyn Inode>, _new_name: &str) -> Result<()> {
+        self.modify_dir()
+    }
+
+    fn read_link(&self) -> Result<SymbolicLink> {
+        match &self.data {
+            InodeData::Symlink(target) => Ok(SymbolicLink::Plain(target.clone())),
+            _ => return_errno!(Errno::EINVAL),
+        }
+    }
+
+    fn write_link(&self, _target: &str) -> Result<()> {
+        return_errno_with_message!(Errno::EROFS, "iso9660 is read-only")
+    }
+
+    fn fs(&self) -> Arc<dyn FileSystem> {
+        self.fs()
+    }
+
+    fn extension(&self) -> &Extension {
+        &self.extension
+    }
+}
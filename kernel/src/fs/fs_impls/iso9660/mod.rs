@@ -0,0 +1,401 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! A read-only ISO9660 filesystem, i.e., the filesystem of CDs and DVDs.
+//!
+//! The features of this version of ISO9660 are as follows:
+//! 1. Rock Ridge. The POSIX names, permissions, owners, timestamps, symbolic links
+//!    and device files are given by the Rock Ridge entries, including those in the
+//!    continuation areas. The deep directories that are relocated by Rock Ridge are
+//!    shown at their original places.
+//! 2. Joliet. The Unicode names of the Joliet directory hierarchy are used if the
+//!    image has no Rock Ridge entries.
+//! 3. Multi-extent files, whose data are described by several directory records.
+//! 4. Deep integration with PageCache. The data of regular files are read through
+//!    the page caches of the inodes.
+//!
+//! Rock Ridge is preferred to Joliet as Linux does. Without both extensions, the
+//! names are lowercased and their version suffixes are removed.
+//!
+//! # Limitation
+//!
+//! Here we summarizes the features that need to be implemented in the future.
+//! 1. Supports the mount options, e.g., `norock`, `nojoliet`, `uid` and `mode`.
+//! 2. Supports multi-session discs and the zisofs compression.
+//! 3. Gives the hard links of Rock Ridge the same inode numbers.
+
+pub use fs::Iso9660Fs;
+
+use crate::fs::iso9660::fs::Iso9660Type;
+
+mod fs;
+mod inode;
+mod record;
+mod rock_ridge;
+
+pub(super) fn init() {
+    crate::fs::vfs::registry::register(&Iso9660Type).unwrap();
+}
+
+#[cfg(ktest)]
+mod test {
+    use alloc::fmt::Debug;
+
+    use aster_block::{
+        BlockDevice, BlockDeviceMeta, SECTOR_SIZE,
+        bio::{BioEnqueueError, BioStatus, BioType, SubmittedBio},
+    };
+    use device_id::DeviceId;
+    use ostd::{
+        mm::{FrameAllocOptions, PAGE_SIZE, Segment, VmIo, io::util::HasVmReaderWriter},
+        prelude::*,
+    };
+
+    use super::fs::Iso9660Fs;
+    use crate::{
+        fs::{
+            file::{InodeMode, InodeType},
+            vfs::{file_system::FileSystem, inode::Inode},
+        },
+        prelude::*,
+        process::Uid,
+    };
+
+    /// A read-only block device whose sectors are kept in memory.
+    struct IsoMemoryDisk(Segment<()>);
+
+    impl Debug for IsoMemoryDisk {
+        fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+            f.debug_struct("IsoMemoryDisk")
+                .field("sectors_count", &(self.0.size() / SECTOR_SIZE))
+                .finish()
+        }
+    }
+
+    impl BlockDevice for IsoMemoryDisk {
+        fn enqueue(&self, bio: SubmittedBio) -> core::prelude::v1::Result<(), BioEnqueueError> {
+            let mut cur_device_ofs = bio.sid_range().start.to_raw() as usize * SECTOR_SIZE;
+            for seg in bio.segments() {
+                let size = match bio.type_() {
+                    BioType::Read => seg
+                        .inner_dma()
+                        .writer()
+                        .unwrap()
+                        .write(self.0.reader().skip(cur_device_ofs)),
+                    _ => 0,
+                };
+                cur_device_ofs += size;
+            }
+            bio.complete(BioStatus::Complete);
+            Ok(())
+        }
+
+        fn metadata(&self) -> BlockDeviceMeta {
+            BlockDeviceMeta {
+                max_nr_segments_per_bio: usize::MAX,
+                nr_sectors: self.0.size() / SECTOR_SIZE,
+                supports_fua: false,
+                max_discard_sectors: 0,
+            }
+        }
+
+        fn name(&self) -> &str {
+            todo!()
+        }
+
+        fn id(&self) -> DeviceId {
+            todo!()
+        }
+    }
+
+    const BLOCK_SIZE: usize = 2048;
+
+    /// The logical blocks of the test image.
+    const PRIMARY_BLOCK: usize = 16;
+    const TERMINATOR_BLOCK: usize = 17;
+    const ROOT_BLOCK: usize = 18;
+    const SUB_BLOCK: usize = 19;
+    const HELLO_BLOCK: usize = 20;
+    const NESTED_BLOCK: usize = 22;
+    const NR_BLOCKS: usize = 23;
+    /// A logical block that is far beyond the end of the image.
+    const BAD_BLOCK: u32 = 1000;
+
+    const HELLO_LEN: usize = 3000;
+    const NESTED_DATA: &[u8] = b"nested file\n";
+
+    const DIRECTORY: u8 = 0x02;
+
+    /// The content of `hello.txt`, which spans two logical blocks.
+    fn hello_data() -> Vec<u8> {
+        (0..HELLO_LEN).map(|i| (i % 251) as u8).collect()
+    }
+
+    /// Appends a directory record.
+    fn push_record(
+        dir: &mut Vec<u8>,
+        block: usize,
+        data_len: usize,
+        flags: u8,
+        name: &[u8],
+        system_use: &[u8],
+    ) {
+        let system_use_start = (33 + name.len()).next_multiple_of(2);
+        let len = (system_use_start + system_use.len()).next_multiple_of(2);
+        let mut record = vec![0u8; len];
+        record[0] = len as u8;
+        record[2..6].copy_from_slice(&(block as u32).to_le_bytes());
+        record[6..10].copy_from_slice(&(block as u32).to_be_bytes());
+        record[10..14].copy_from_slice(&(data_len as u32).to_le_bytes());
+        record[14..18].copy_from_slice(&(data_len as u32).to_be_bytes());
+        // 2026-01-01 00:00:00 UTC.
+        record[18..21].copy_from_slice(&[126, 1, 1]);
+        record[25] = flags;
+        record[28..30].copy_from_slice(&1u16.to_le_bytes());
+        record[32] = name.len() as u8;
+        record[33..33 + name.len()].copy_from_slice(name);
+        record[system_use_start..system_use_start + system_use.len()].copy_from_slice(system_use);
+        dir.extend_from_slice(&record);
+    }
+
+    /// Returns the Rock Ridge `PX` and `NM` entries of a file.
+    fn rock_ridge_entries(mode: u32, uid: u32, name: &str) -> Vec<u8> {
+        let mut entries = vec![b'P', b'X', 36, 1];
+        for value in [mode, 1, uid, uid] {
+            entries.extend_from_slice(&value.to_le_bytes());
+            entries.extend_from_slice(&value.to_be_bytes());
+        }
+        entries.extend_from_slice(&[b'N', b'M', 5 + name.len() as u8, 1, 0]);
+        entries.extend_from_slice(name.as_bytes());
+        entries
+    }
+
+    /// Builds an image with `hello.txt` in the root directory and `nested.txt` in the
+    /// subdirectory `sub`.
+    ///
+    /// With Rock Ridge, they are named `Hello World.txt`, `Sub Dir` and `Nested.txt`.
+    fn build_image(rock_ridge: bool) -> Vec<u8> {
+        let mut image = vec![0u8; NR_BLOCKS * BLOCK_SIZE];
+        let entries = |mode: u32, name: &str| {
+            if rock_ridge {
+                rock_ridge_entries(mode, 1000, name)
+            } else {
+                Vec::new()
+            }
+        };
+
+        let mut root = Vec::new();
+        // The `SP` entry of the `.` record of the root indicates Rock Ridge.
+        let root_dot_entries = if rock_ridge {
+            vec![b'S', b'P', 7, 1, 0xBE, 0xEF, 0]
+        } else {
+            Vec::new()
+        };
+        push_record(
+            &mut root,
+            ROOT_BLOCK,
+            BLOCK_SIZE,
+            DIRECTORY,
+            &[0],
+            &root_dot_entries,
+        );
+        push_record(&mut root, ROOT_BLOCK, BLOCK_SIZE, DIRECTORY, &[1], &[]);
+        push_record(
+            &mut root,
+            HELLO_BLOCK,
+            HELLO_LEN,
+            0,
+            b"HELLO.TXT;1",
+            &entries(0o100640, "Hello World.txt"),
+        );
+        push_record(
+            &mut root,
+            SUB_BLOCK,
+            BLOCK_SIZE,
+            DIRECTORY,
+            b"SUB",
+            &entries(0o040750, "Sub Dir"),
+        );
+        image[ROOT_BLOCK * BLOCK_SIZE..][..root.len()].copy_from_slice(&root);
+
+        let mut sub = Vec::new();
+        push_record(&mut sub, SUB_BLOCK, BLOCK_SIZE, DIRECTORY, &[0], &[]);
+        push_record(&mut sub, ROOT_BLOCK, BLOCK_SIZE, DIRECTORY, &[1], &[]);
+        push_record(
+            &mut sub,
+            NESTED_BLOCK,
+            NESTED_DATA.len(),
+            0,
+            b"NESTED.TXT;1",
+            &entries(0o100600, "Nested.txt"),
+        );
+        image[SUB_BLOCK * BLOCK_SIZE..][..sub.len()].copy_from_slice(&sub);
+
+        image[HELLO_BLOCK * BLOCK_SIZE..][..HELLO_LEN].copy_from_slice(&hello_data());
+        image[NESTED_BLOCK * BLOCK_SIZE..][..NESTED_DATA.len()].copy_from_slice(NESTED_DATA);
+
+        let primary = &mut image[PRIMARY_BLOCK * BLOCK_SIZE..][..BLOCK_SIZE];
+        primary[0] = 1;
+        primary[1..6].copy_from_slice(b"CD001");
+        primary[6] = 1;
+        primary[80..84].copy_from_slice(&(NR_BLOCKS as u32).to_le_bytes());
+        primary[84..88].copy_from_slice(&(NR_BLOCKS as u32).to_be_bytes());
+        primary[128..130].copy_from_slice(&(BLOCK_SIZE as u16).to_le_bytes());
+        primary[130..132].copy_from_slice(&(BLOCK_SIZE as u16).to_be_bytes());
+        let mut root_record = Vec::new();
+        push_record(
+            &mut root_record,
+            ROOT_BLOCK,
+            BLOCK_SIZE,
+            DIRECTORY,
+            &[0],
+            &[],
+        );
+        primary[156..190].copy_from_slice(&root_record);
+
+        let terminator = &mut image[TERMINATOR_BLOCK * BLOCK_SIZE..][..BLOCK_SIZE];
+        terminator[0] = 255;
+        terminator[1..6].copy_from_slice(b"CD001");
+        terminator[6] = 1;
+
+        image
+    }
+
+    fn open(image: &[u8]) -> Result<Arc<Iso9660Fs>> {
+        let segment = FrameAllocOptions::new()
+            .alloc_segment(image.len().div_ceil(PAGE_SIZE))
+            .unwrap();
+        segment.write_bytes(0, image).unwrap();
+        Iso9660Fs::open(Arc::new(IsoMemoryDisk(segment)))
+    }
+
+    fn list_names(dir: &Arc<dyn Inode>) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        dir.readdir_at(0, &mut names).unwrap();
+        names
+    }
+
+    fn read_all(file: &Arc<dyn Inode>) -> Vec<u8> {
+        let mut buf = vec![0u8; file.size()];
+        assert_eq!(file.read_bytes_at(0, &mut buf).unwrap(), buf.len());
+        buf
+    }
+
+    #[ktest]
+    fn lookup_and_read() {
+        let fs = open(&build_image(false)).unwrap();
+        let root = fs.root_inode();
+
+        // The plain names are lowercased and lose their versions.
+        assert_eq!(list_names(&root), [".", "..", "hello.txt", "sub"]);
+        assert_eq!(
+            root.lookup("HELLO.TXT;1").unwrap_err().error(),
+            Errno::ENOENT
+        );
+
+        let hello = root.lookup("hello.txt").unwrap();
+        assert_eq!(hello.type_(), InodeType::File);
+        assert_eq!(hello.mode().unwrap().bits(), 0o444);
+        assert_eq!(read_all(&hello), hello_data());
+
+        let sub = root.lookup("sub").unwrap();
+        assert_eq!(sub.type_(), InodeType::Dir);
+        assert_eq!(list_names(&sub), [".", "..", "nested.txt"]);
+        let nested = sub.lookup("nested.txt").unwrap();
+        assert_eq!(read_all(&nested), NESTED_DATA);
+
+        // Reading past the end stops at the end of the file.
+        let mut buf = vec![0u8; 100];
+        assert_eq!(hello.read_bytes_at(HELLO_LEN - 10, &mut buf).unwrap(), 10);
+        assert_eq!(hello.read_bytes_at(HELLO_LEN, &mut buf).unwrap(), 0);
+
+        assert_eq!(
+            root.create("new", InodeType::File, InodeMode::all())
+                .unwrap_err()
+                .error(),
+            Errno::EROFS
+        );
+    }
+
+    #[ktest]
+    fn rock_ridge() {
+        let fs = open(&build_image(true)).unwrap();
+        let root = fs.root_inode();
+        assert_eq!(list_names(&root), [".", "..", "Hello World.txt", "Sub Dir"]);
+
+        let hello = root.lookup("Hello World.txt").unwrap();
+        assert_eq!(hello.mode().unwrap().bits(), 0o640);
+        assert_eq!(hello.owner().unwrap(), Uid::new(1000));
+        assert_eq!(read_all(&hello), hello_data());
+
+        let sub = root.lookup("Sub Dir").unwrap();
+        assert_eq!(sub.type_(), InodeType::Dir);
+        assert_eq!(sub.mode().unwrap().bits(), 0o750);
+        let nested = sub.lookup("Nested.txt").unwrap();
+        assert_eq!(read_all(&nested), NESTED_DATA);
+    }
+
+    #[ktest]
+    fn malformed_volume_descriptor() {
+        let primary = PRIMARY_BLOCK * BLOCK_SIZE;
+
+        let mut image = build_image(false);
+        image[primary + 1..primary + 6].copy_from_slice(b"CD002");
+        assert_eq!(open(&image).unwrap_err().error(), Errno::EINVAL);
+
+        // Only the terminator is left.
+        let mut image = build_image(false);
+        image[primary] = 255;
+        assert_eq!(open(&image).unwrap_err().error(), Errno::EINVAL);
+
+        let mut image = build_image(false);
+        image[primary + 128..primary + 130].copy_from_slice(&3000u16.to_le_bytes());
+        assert_eq!(open(&image).unwrap_err().error(), Errno::EINVAL);
+
+        // The root directory is beyond the device.
+        let mut image = build_image(false);
+        image[primary + 156 + 2..primary + 156 + 6].copy_from_slice(&BAD_BLOCK.to_le_bytes());
+        assert_eq!(open(&image).unwrap_err().error(), Errno::EIO);
+
+        // The root directory is truncated by the device.
+        let image = build_image(false);
+        assert_eq!(
+            open(&image[..ROOT_BLOCK * BLOCK_SIZE]).unwrap_err().error(),
+            Errno::EIO
+        );
+    }
+
+    #[ktest]
+    fn malformed_directory() {
+        let root = ROOT_BLOCK * BLOCK_SIZE;
+
+        // The third record, which is `hello.txt`, is shorter than the fixed part.
+        let mut image = build_image(false);
+        let hello_record =
+            root + image[root] as usize + image[root + image[root] as usize] as usize;
+        image[hello_record] = 20;
+        assert_eq!(open(&image).unwrap_err().error(), Errno::EIO);
+
+        // The name of `hello.txt` runs past the end of its record.
+        let mut image = build_image(false);
+        image[hello_record + 32] = 200;
+        assert_eq!(open(&image).unwrap_err().error(), Errno::EIO);
+
+        // The extent of `hello.txt` is beyond the device, which fails the reads only.
+        let mut image = build_image(false);
+        image[hello_record + 2..hello_record + 6].copy_from_slice(&BAD_BLOCK.to_le_bytes());
+        let fs = open(&image).unwrap();
+        let hello = fs.root_inode().lookup("hello.txt").unwrap();
+        let mut buf = vec![0u8; HELLO_LEN];
+        assert!(hello.read_bytes_at(0, &mut buf).is_err());
+
+        // The subdirectory is too large to be loaded.
+        let mut image = build_image(false);
+        let sub_record = hello_record + image[hello_record] as usize;
+        image[sub_record + 10..sub_record + 14].copy_from_slice(&u32::MAX.to_le_bytes());
+        let fs = open(&image).unwrap();
+        assert_eq!(
+            fs.root_inode().lookup("sub").unwrap_err().error(),
+            Errno::EIO
+        );
+    }
+}
@@ -0,0 +1,174 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use time::{Date, Month, PrimitiveDateTime, Time};
+
+use crate::prelude::*;
+
+/// The size of the fixed part of a directory record, excluding the name.
+const RECORD_HEADER_LEN: usize = 33;
+
+bitflags! {
+    /// The flags of a directory record.
+    pub(super) struct RecordFlags: u8 {
+        const HIDDEN = 0x01;
+        const DIRECTORY = 0x02;
+        /// The record describes an associated file, e.g., a Macintosh resource fork.
+        const ASSOCIATED = 0x04;
+        const RECORD = 0x08;
+        const PROTECTION = 0x10;
+        /// The file has more extents, which are described by the following records.
+        const MULTI_EXTENT = 0x80;
+    }
+}
+
+/// A directory record, which describes an extent of a file.
+#[derive(Debug)]
+pub(super) struct DirRecord<'a> {
+    /// The logical block of the extent.
+    pub(super) extent: u32,
+    pub(super) data_len: u32,
+    pub(super) time: Duration,
+    pub(super) flags: RecordFlags,
+    pub(super) name: &'a [u8],
+    /// The system use area, which holds the Rock Ridge entries.
+    pub(super) system_use: &'a [u8],
+}
+
+impl<'a> DirRecord<'a> {
+    /// Parses the record at the start of `buf`, whose length is given by its first byte.
+    pub(super) fn parse(buf: &'a [u8]) -> Result<Self> {
+        let len = buf.first().copied().unwrap_or(0) as usize;
+        if len < RECORD_HEADER_LEN + 1 || len > buf.len() {
+            return_errno_with_message!(Errno::EIO, "invalid directory record length");
+        }
+        let buf = &buf[..len];
+
+        let name_len = buf[32] as usize;
+        let name_end = RECORD_HEADER_LEN + name_len;
+        if name_end > len {
+            return_errno_with_message!(Errno::EIO, "invalid directory record name");
+        }
+        // The name is padded so that the system use area starts at an even offset.
+        let system_use_start = name_end.next_multiple_of(2).min(len);
+
+        Ok(Self {
+            extent: read_le_u32(buf, 2),
+            data_len: read_le_u32(buf, 10),
+            time: parse_short_time(&buf[18..25]),
+            flags: RecordFlags::from_bits_truncate(buf[25]),
+            name: &buf[RECORD_HEADER_LEN..name_end],
+            system_use: &buf[system_use_start..],
+        })
+    }
+
+    /// Returns whether the record is the `.` or the `..` entry.
+    pub(super) fn is_dot_or_dotdot(&self) -> bool {
+        self.name == [0] || self.name == [1]
+    }
+}
+
+/// Reads the little-endian half of a both-endian or a little-endian `u32` field.
+pub(super) fn read_le_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+/// Reads the little-endian half of a both-endian or a little-endian `u16` field.
+pub(super) fn read_le_u16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(buf[offset..offset + 2].try_into().unwrap())
+}
+
+/// Converts a plain ISO9660 name, removing the version and lowercasing it as Linux does.
+pub(super) fn plain_name(name: &[u8]) -> String {
+    let name = strip_version(name);
+    name.iter()
+        .map(|&byte| byte.to_ascii_lowercase() as char)
+        .collect()
+}
+
+/// Converts a Joliet name, which is encoded in UCS-2 in big endian.
+pub(super) fn joliet_name(name: &[u8]) -> String {
+    let units = name
+        .chunks_exact(2)
+        .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+        .collect::<Vec<_>>();
+    let name = char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect::<String>();
+    let len = strip_version(name.as_bytes()).len();
+    String::from(&name[..len])
+}
+
+/// Removes the `;<version>` suffix and a trailing `.` that denotes an empty extension.
+fn strip_version(name: &[u8]) -> &[u8] {
+    let name = match name.iter().rposition(|&byte| byte == b';') {
+        Some(pos) => &name[..pos],
+        None => name,
+    };
+    match name.split_last() {
+        Some((&last, rest)) if last == b'.' && !rest.is_empty() => rest,
+        _ => name,
+    }
+}
+
+/// Parses a 7-byte timestamp of a directory record or a short Rock Ridge timestamp.
+pub(super) fn parse_short_time(buf: &[u8]) -> Duration {
+    to_duration(
+        1900 + buf[0] as i32,
+        buf[1],
+        buf[2],
+        buf[3],
+        buf[4],
+        buf[5],
+        buf[6] as i8,
+    )
+}
+
+/// Parses a 17-byte timestamp of a volume descriptor or a long Rock Ridge timestamp.
+pub(super) fn parse_long_time(buf: &[u8]) -> Duration {
+    let digits = |range: core::ops::Range<usize>| {
+        buf[range].iter().fold(0u32, |value, &byte| {
+            value * 10 + (byte.wrapping_sub(b'0') as u32).min(9)
+        })
+    };
+    to_duration(
+        digits(0..4) as i32,
+        digits(4..6) as u8,
+        digits(6..8) as u8,
+        digits(8..10) as u8,
+        digits(10..12) as u8,
+        digits(12..14) as u8,
+        buf[16] as i8,
+    )
+}
+
+/// Converts a date and a time to a time since the Unix epoch.
+///
+/// `gmt_offset` is the offset from UTC in units of 15 minutes. Invalid or pre-epoch
+/// timestamps are converted to the epoch.
+fn to_duration(
+    year: i32,
+    month: u8,
+    day: u8,
+    hour: u8,
+    minute: u8,
+    second: u8,
+    gmt_offset: i8,
+) -> Duration {
+    let Ok(month) = Month::try_from(month) else {
+        return Duration::ZERO;
+    };
+    let Ok(date) = Date::from_calendar_date(year, month, day) else {
+        return Duration::ZERO;
+    };
+    let Ok(time) = Time::from_hms(hour, minute, second) else {
+        return Duration::ZERO;
+    };
+
+    let secs = PrimitiveDateTime::new(date, time)
+        .assume_utc()
+        .unix_timestamp()
+        - gmt_offset as i64 * 15 * 60;
+    Duration::from_secs(secs.max(0) as u64)
+}
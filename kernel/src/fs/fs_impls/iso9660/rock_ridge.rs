@@ -0,0 +1,192 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use super::{
+    fs::Iso9660Fs,
+    record::{parse_long_time, parse_short_time, read_le_u32},
+};
+use crate::prelude::*;
+
+/// The maximum number of continuation areas that are followed for a record,
+/// which prevents loops in corrupted images.
+const MAX_CONTINUATIONS: usize = 32;
+
+/// The flags of the `NM` and `SL` entries.
+const CONTINUE: u8 = 0x01;
+const CURRENT: u8 = 0x02;
+const PARENT: u8 = 0x04;
+const ROOT: u8 = 0x08;
+
+/// The flag of the `TF` entries that indicates the long timestamp format.
+const LONG_FORM: u8 = 0x80;
+
+/// The attributes of a file that are given by the Rock Ridge entries of its record.
+#[derive(Debug, Clone, Default)]
+pub(super) struct RockRidge {
+    pub(super) name: Option<String>,
+    /// The file mode, including the file type.
+    pub(super) mode: Option<u16>,
+    pub(super) nlink: Option<u32>,
+    pub(super) uid: Option<u32>,
+    pub(super) gid: Option<u32>,
+    /// The major and the minor numbers of a device.
+    pub(super) device: Option<(u32, u32)>,
+    pub(super) symlink: Option<String>,
+    pub(super) atime: Option<Duration>,
+    pub(super) mtime: Option<Duration>,
+    pub(super) ctime: Option<Duration>,
+    /// The logical block of a directory that has been relocated to elsewhere.
+    pub(super) child_link: Option<u32>,
+    /// Whether this is a relocated directory, which is hidden from its actual parent.
+    pub(super) relocated: bool,
+}
+
+impl RockRidge {
+    /// Returns the number of bytes that are skipped at the start of the system use areas
+    /// if the `SP` entry of the root directory indicates that Rock Ridge is in use.
+    pub(super) fn detect(root_system_use: &[u8]) -> Option<usize> {
+        match root_system_use {
+            [b'S', b'P', 7, _, 0xBE, 0xEF, skip, ..] => Some(*skip as usize),
+            _ => None,
+        }
+    }
+
+    /// Parses the entries of a system use area, following the continuation areas.
+    pub(super) fn parse(fs: &Iso9660Fs, system_use: &[u8]) -> Result<Self> {
+        let mut rock_ridge = Self::default();
+        let mut name = Vec::new();
+        let mut name_done = false;
+        let mut symlink = SymlinkBuilder::default();
+
+        let mut area = Vec::from(system_use);
+        for _ in 0..MAX_CONTINUATIONS {
+            let mut continuation = None;
+
+            let mut pos = 0;
+            while pos + 4 <= area.len() {
+                let len = area[pos + 2] as usize;
+                if len < 4 || pos + len > area.len() {
+                    break;
+                }
+                let entry = &area[pos..pos + len];
+                pos += len;
+
+                match &entry[..2] {
+                    b"PX" if len >= 36 => {
+                        rock_ridge.mode = Some(read_le_u32(entry, 4) as u16);
+                        rock_ridge.nlink = Some(read_le_u32(entry, 12));
+                        rock_ridge.uid = Some(read_le_u32(entry, 20));
+                        rock_ridge.gid = Some(read_le_u32(entry, 28));
+                    }
+                    b"PN" if len >= 20 => {
+                        let high = read_le_u32(entry, 4);
+                        let low = read_le_u32(entry, 12);
+                        // Old images store the device number in the low half in the 8:8 format.
+                        rock_ridge.device = if high == 0 && low & !0xFF != 0 {
+                            Some((low >> 8, low & 0xFF))
+                        } else {
+                            Some((high, low))
+                        };
+                    }
+                    b"NM" if len >= 5 => {
+                        let flags = entry[4];
+                        if !name_done && flags & (CURRENT | PARENT) == 0 {
+                            name.extend_from_slice(&entry[5..]);
+                            name_done = flags & CONTINUE == 0;
+                        }
+                    }
+                    b"SL" if len >= 5 => symlink.push_components(&entry[5..]),
+                    b"TF" if len >= 5 => rock_ridge.parse_times(entry),
+                    b"CL" if len >= 12 => rock_ridge.child_link = Some(read_le_u32(entry, 4)),
+                    b"RE" => rock_ridge.relocated = true,
+                    b"CE" if len >= 28 => {
+                        let block = read_le_u32(entry, 4) as u64;
+                        let offset = read_le_u32(entry, 12) as u64;
+                        let len = read_le_u32(entry, 20) as usize;
+                        continuation = Some((block * fs.block_size() as u64 + offset, len));
+                    }
+                    b"ST" => break,
+                    _ => {}
+                }
+            }
+
+            let Some((offset, len)) = continuation else {
+                break;
+            };
+            if len > fs.block_size() {
+                return_errno_with_message!(Errno::EIO, "invalid Rock Ridge continuation area");
+            }
+            area = vec![0u8; len];
+            fs.read_bytes(offset, &mut area)?;
+        }
+
+        if !name.is_empty() {
+            rock_ridge.name = Some(String::from_utf8_lossy(&name).into_owned());
+        }
+        rock_ridge.symlink = symlink.target;
+        Ok(rock_ridge)
+    }
+
+    /// Parses the timestamps of a `TF` entry, which are in the order of the flag bits.
+    fn parse_times(&mut self, entry: &[u8]) {
+        let flags = entry[4];
+        let stamp_len = if flags & LONG_FORM != 0 { 17 } else { 7 };
+        let mut stamps = entry[5..].chunks_exact(stamp_len);
+        for bit in 0..7 {
+            if flags & (1 << bit) == 0 {
+                continue;
+            }
+            let Some(stamp) = stamps.next() else {
+                return;
+            };
+            let time = if flags & LONG_FORM != 0 {
+                parse_long_time(stamp)
+            } else {
+                parse_short_time(stamp)
+            };
+            match bit {
+                1 => self.mtime = Some(time),
+                2 => self.atime = Some(time),
+                3 => self.ctime = Some(time),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// The target of a symbolic link that is assembled from the `SL` entries.
+#[derive(Default)]
+struct SymlinkBuilder {
+    target: Option<String>,
+    /// Whether a separator is needed before the next component.
+    need_separator: bool,
+}
+
+impl SymlinkBuilder {
+    fn push_components(&mut self, mut components: &[u8]) {
+        let target = self.target.get_or_insert_with(String::new);
+        while let [flags, len, rest @ ..] = components {
+            let len = (*len as usize).min(rest.len());
+            let content = &rest[..len];
+            components = &rest[len..];
+
+            if flags & ROOT != 0 {
+                target.push('/');
+                self.need_separator = false;
+                continue;
+            }
+            if self.need_separator {
+                target.push('/');
+            }
+            if flags & CURRENT != 0 {
+                target.push('.');
+            } else if flags & PARENT != 0 {
+                target.push_str("..");
+            } else {
+                target.push_str(&String::from_utf8_lossy(content));
+            }
+            self.need_separator = flags & CONTINUE == 0;
+        }
+    }
+}
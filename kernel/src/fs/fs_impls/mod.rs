@@ -6,29 +6,45 @@
 
 pub mod cgroupfs;
 pub mod configfs;
+pub mod debugfs;
 pub mod devpts;
+pub mod devtmpfs;
 pub mod exfat;
 pub mod ext2;
+pub mod fuse;
+pub mod iso9660;
 pub mod overlayfs;
 pub mod procfs;
 pub mod pseudofs;
 pub mod ramfs;
+pub mod securityfs;
+pub mod squashfs;
 pub mod sysfs;
 pub mod tmpfs;
+pub mod tracefs;
+pub mod vfat;
 
 pub(super) fn init() {
     sysfs::init();
     procfs::init();
     cgroupfs::init();
     configfs::init();
+    debugfs::init();
     ramfs::init();
+    securityfs::init();
     tmpfs::init();
+    tracefs::init();
+    devtmpfs::init();
     devpts::init();
     pseudofs::init();
 
     ext2::init();
     exfat::init();
+    vfat::init();
+    iso9660::init();
+    squashfs::init();
     overlayfs::init();
+    fuse::init();
 }
 
 pub(super) fn init_on_each_cpu() {
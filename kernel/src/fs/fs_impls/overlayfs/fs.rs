@@ -1148,6 +1148,7 @@ impl FsType for OverlayFsType {
     fn create(
         &self,
         _flags: FsFlags,
+        _source: Option<&str>,
         args: Option<CString>,
         _disk: Option<Arc<dyn aster_block::BlockDevice>>,
     ) -> Result<Arc<dyn FileSystem>> {
@@ -0,0 +1,125 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_util::slot_vec::SlotVec;
+use ostd::{irq::IrqLine, sync::RwMutexUpgradeableGuard};
+
+use self::smp_affinity::{SmpAffinityFileOps, SmpAffinityListFileOps};
+use crate::{
+    fs::{
+        file::mkmod,
+        procfs::template::{
+            DirOps, ProcDir, ProcDirBuilder, lookup_child_from_table, populate_children_from_table,
+        },
+        utils::DirEntryVecExt,
+        vfs::inode::Inode,
+    },
+    prelude::*,
+};
+
+mod smp_affinity;
+
+/// Represents the inode at `/proc/irq`.
+pub struct IrqDirOps;
+
+impl IrqDirOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/kernel/irq/proc.c#L436>
+        ProcDirBuilder::new(Self, mkmod!(a+rx))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl DirOps for IrqDirOps {
+    fn lookup_child(&self, dir: &ProcDir<Self>, name: &str) -> Result<Arc<dyn Inode>> {
+        let Ok(irq_num) = name.parse::<u8>() else {
+            return_errno_with_message!(Errno::ENOENT, "the name is not a valid IRQ number");
+        };
+        if IrqLine::lookup(irq_num).is_none() {
+            return_errno_with_message!(Errno::ENOENT, "the IRQ line is not allocated");
+        }
+
+        let mut cached_children = dir.cached_children().write();
+        Ok(cached_children
+            .put_entry_if_not_found(name, || {
+                IrqNumDirOps::new_inode(irq_num, dir.this_weak().clone())
+            })
+            .clone())
+    }
+
+    fn populate_children<'a>(
+        &self,
+        dir: &'a ProcDir<Self>,
+    ) -> RwMutexUpgradeableGuard<'a, SlotVec<(String, Arc<dyn Inode>)>> {
+        let mut cached_children = dir.cached_children().write();
+
+        // Remove outdated entries.
+        for i in 0..cached_children.slots_len() {
+            let Some((name, _)) = cached_children.get(i) else {
+                continue;
+            };
+            if IrqLine::lookup(name.parse().unwrap()).is_none() {
+                cached_children.remove(i);
+            }
+        }
+
+        // Add new entries.
+        for irq_num in (0..=u8::MAX).filter(|num| IrqLine::lookup(*num).is_some()) {
+            cached_children.put_entry_if_not_found(&irq_num.to_string(), || {
+                IrqNumDirOps::new_inode(irq_num, dir.this_weak().clone())
+            });
+        }
+
+        cached_children.downgrade()
+    }
+}
+
+/// Represents the inode at `/proc/irq/[n]`.
+struct IrqNumDirOps(u8);
+
+impl IrqNumDirOps {
+    fn new_inode(irq_num: u8, parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/kernel/irq/proc.c#L340>
+        ProcDirBuilder::new(Self(irq_num), mkmod!(a+rx))
+            .parent(parent)
+            .volatile()
+            .build()
+            .unwrap()
+    }
+
+    #[expect(clippy::type_complexity)]
+    const STATIC_ENTRIES: &'static [(&'static str, fn(u8, Weak<dyn Inode>) -> Arc<dyn Inode>)] = &[
+        ("smp_affinity", SmpAffinityFileOps::new_inode),
+        ("smp_affinity_list", SmpAffinityListFileOps::new_inode),
+    ];
+}
+
+impl DirOps for IrqNumDirOps {
+    fn lookup_child(&self, dir: &ProcDir<Self>, name: &str) -> Result<Arc<dyn Inode>> {
+        let mut cached_children = dir.cached_children().write();
+
+        if let Some(child) =
+            lookup_child_from_table(name, &mut cached_children, Self::STATIC_ENTRIES, |f| {
+                (f)(self.0, dir.this_weak().clone())
+            })
+        {
+            return Ok(child);
+        }
+
+        return_errno_with_message!(Errno::ENOENT, "the file does not exist");
+    }
+
+    fn populate_children<'a>(
+        &self,
+        dir: &'a ProcDir<Self>,
+    ) -> RwMutexUpgradeableGuard<'a, SlotVec<(String, Arc<dyn Inode>)>> {
+        let mut cached_children = dir.cached_children().write();
+
+        populate_children_from_table(&mut cached_children, Self::STATIC_ENTRIES, |f| {
+            (f)(self.0, dir.this_weak().clone())
+        });
+
+        cached_children.downgrade()
+    }
+}
@@ -0,0 +1,159 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_util::printer::VmPrinter;
+use ostd::{
+    cpu::{CpuId, num_cpus},
+    irq::IrqLine,
+    util::id_set::Id,
+};
+
+use crate::{
+    cpu::hotplug::is_cpu_online,
+    fs::{
+        file::mkmod,
+        procfs::template::{FileOps, ProcFileBuilder},
+        vfs::inode::Inode,
+    },
+    prelude::*,
+};
+
+/// The maximum length of the CPU mask or list that can be written.
+const MAX_CPUS_STR_LEN: usize = 4096;
+
+/// Represents the inode at `/proc/irq/[n]/smp_affinity`.
+pub struct SmpAffinityFileOps(u8);
+
+impl SmpAffinityFileOps {
+    pub fn new_inode(irq_num: u8, parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/kernel/irq/proc.c#L373>
+        ProcFileBuilder::new(Self(irq_num), mkmod!(a+r, u+w))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for SmpAffinityFileOps {
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        // The mask is printed in hexadecimal, with a comma between every 32 bits.
+        let cpu_index = lookup_irq_line(self.0)?.affinity().as_usize();
+        let num_digits = num_cpus().div_ceil(4);
+        for i in (0..num_digits).rev() {
+            let digit = if i == cpu_index / 4 {
+                1 << (cpu_index % 4)
+            } else {
+                0
+            };
+            write!(printer, "{:x}", digit)?;
+            if i != 0 && i % 8 == 0 {
+                write!(printer, ",")?;
+            }
+        }
+        writeln!(printer)?;
+
+        Ok(printer.bytes_written())
+    }
+
+    fn write_at(&self, _offset: usize, reader: &mut VmReader) -> Result<usize> {
+        let (cstr, read_bytes) = reader.read_cstring_until_end(MAX_CPUS_STR_LEN)?;
+        let mask = cstr
+            .to_str()
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the CPU mask is not valid UTF-8"))?
+            .trim();
+
+        // Find the lowest CPU in the mask, whose digits are ordered from the highest to the
+        // lowest, ignoring the commas.
+        let digits = mask.chars().rev().filter(|ch| *ch != ',');
+        let mut cpu_index = None;
+        for (i, ch) in digits.enumerate() {
+            let Some(digit) = ch.to_digit(16) else {
+                return_errno_with_message!(Errno::EINVAL, "the CPU mask is not hexadecimal");
+            };
+            if cpu_index.is_none() && digit != 0 {
+                cpu_index = Some(i * 4 + digit.trailing_zeros() as usize);
+            }
+        }
+
+        set_affinity(self.0, cpu_index)?;
+
+        Ok(read_bytes)
+    }
+}
+
+/// Represents the inode at `/proc/irq/[n]/smp_affinity_list`.
+pub struct SmpAffinityListFileOps(u8);
+
+impl SmpAffinityListFileOps {
+    pub fn new_inode(irq_num: u8, parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/kernel/irq/proc.c#L381>
+        ProcFileBuilder::new(Self(irq_num), mkmod!(a+r, u+w))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for SmpAffinityListFileOps {
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        let cpu_id = lookup_irq_line(self.0)?.affinity();
+        writeln!(printer, "{}", cpu_id.as_usize())?;
+
+        Ok(printer.bytes_written())
+    }
+
+    fn write_at(&self, _offset: usize, reader: &mut VmReader) -> Result<usize> {
+        let (cstr, read_bytes) = reader.read_cstring_until_end(MAX_CPUS_STR_LEN)?;
+        let list = cstr
+            .to_str()
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the CPU list is not valid UTF-8"))?
+            .trim();
+
+        // Find the lowest CPU in the list, e.g., `0-3,8`.
+        let mut cpu_index = None;
+        for range in list.split(',').filter(|range| !range.is_empty()) {
+            let first = range.split_once('-').map_or(range, |(first, _)| first);
+            let Ok(first) = first.trim().parse::<usize>() else {
+                return_errno_with_message!(Errno::EINVAL, "the CPU list is not valid");
+            };
+            cpu_index = Some(cpu_index.map_or(first, |index: usize| index.min(first)));
+        }
+
+        set_affinity(self.0, cpu_index)?;
+
+        Ok(read_bytes)
+    }
+}
+
+fn lookup_irq_line(irq_num: u8) -> Result<IrqLine> {
+    IrqLine::lookup(irq_num)
+        .ok_or_else(|| Error::with_message(Errno::ENOENT, "the IRQ line has been released"))
+}
+
+/// Delivers the interrupts of the IRQ line to the CPU.
+///
+/// The IRQ lines can only target one CPU, so the lowest CPU is chosen if multiple CPUs are
+/// specified.
+fn set_affinity(irq_num: u8, cpu_index: Option<usize>) -> Result<()> {
+    let Some(cpu_id) = cpu_index.and_then(|index| CpuId::try_from(index).ok()) else {
+        return_errno_with_message!(Errno::EINVAL, "no valid CPU is specified");
+    };
+    if !is_cpu_online(cpu_id) {
+        return_errno_with_message!(Errno::EINVAL, "the specified CPU is offline");
+    }
+
+    let irq_line = lookup_irq_line(irq_num)?;
+    if irq_line.is_affinity_managed() {
+        return_errno_with_message!(Errno::EIO, "the affinity is managed by the kernel");
+    }
+
+    irq_line.set_affinity(cpu_id).map_err(|err| match err {
+        ostd::Error::AccessDenied => {
+            Error::with_message(Errno::EIO, "the affinity of the IRQ line cannot be changed")
+        }
+        err => Error::from(err),
+    })
+}
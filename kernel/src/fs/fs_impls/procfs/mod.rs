@@ -7,9 +7,11 @@ use ostd::sync::RwMutexUpgradeableGuard;
 use template::{DirOps, ProcDir, lookup_child_from_table, populate_children_from_table};
 
 use self::{
-    cmdline::CmdLineFileOps, cpuinfo::CpuInfoFileOps, loadavg::LoadAvgFileOps,
-    meminfo::MemInfoFileOps, mounts::MountsSymOps, pid::PidDirOps, self_::SelfSymOps,
-    sys::SysDirOps, thread_self::ThreadSelfSymOps, uptime::UptimeFileOps, version::VersionFileOps,
+    cmdline::CmdLineFileOps, cpuinfo::CpuInfoFileOps, irq::IrqDirOps, loadavg::LoadAvgFileOps,
+    meminfo::MemInfoFileOps, mounts::MountsSymOps, partitions::PartitionsFileOps, pid::PidDirOps,
+    schedstat::SchedStatFileOps, self_::SelfSymOps, slabinfo::SlabInfoFileOps, sys::SysDirOps,
+    thread_self::ThreadSelfSymOps, uptime::UptimeFileOps, version::VersionFileOps,
+    vmcore::VmcoreFileOps,
 };
 use crate::{
     events::Observer,
@@ -24,6 +26,7 @@ use crate::{
             registry::{FsProperties, FsType},
         },
     },
+    power::vmcore::vmcore,
     prelude::*,
     process::{
         Pid,
@@ -34,17 +37,22 @@ use crate::{
 mod cmdline;
 mod cpuinfo;
 mod filesystems;
+mod irq;
 mod loadavg;
 mod meminfo;
 mod mounts;
+mod partitions;
 mod pid;
+mod schedstat;
 mod self_;
+mod slabinfo;
 mod stat;
 mod sys;
 mod template;
 mod thread_self;
 mod uptime;
 mod version;
+mod vmcore;
 
 pub(super) fn init() {
     crate::fs::vfs::registry::register(&ProcFsType).unwrap();
@@ -123,6 +131,7 @@ impl FsType for ProcFsType {
     fn create(
         &self,
         _flags: FsFlags,
+        _source: Option<&str>,
         _args: Option<CString>,
         _disk: Option<Arc<dyn aster_block::BlockDevice>>,
     ) -> Result<Arc<dyn FileSystem>> {
@@ -154,10 +163,14 @@ impl RootDirOps {
         ("cmdline", CmdLineFileOps::new_inode),
         ("cpuinfo", CpuInfoFileOps::new_inode),
         ("filesystems", FileSystemsFileOps::new_inode),
+        ("irq", IrqDirOps::new_inode),
         ("loadavg", LoadAvgFileOps::new_inode),
         ("meminfo", MemInfoFileOps::new_inode),
         ("mounts", MountsSymOps::new_inode),
+        ("partitions", PartitionsFileOps::new_inode),
+        ("schedstat", SchedStatFileOps::new_inode),
         ("self", SelfSymOps::new_inode),
+        ("slabinfo", SlabInfoFileOps::new_inode),
         ("stat", StatFileOps::new_inode),
         ("sys", SysDirOps::new_inode),
         ("thread-self", ThreadSelfSymOps::new_inode),
@@ -204,6 +217,16 @@ impl DirOps for RootDirOps {
             return Ok(child);
         }
 
+        if name == "vmcore"
+            && let Some(vmcore) = vmcore()
+        {
+            return Ok(cached_children
+                .put_entry_if_not_found(name, || {
+                    VmcoreFileOps::new_inode(vmcore, dir.this_weak().clone())
+                })
+                .clone());
+        }
+
         return_errno_with_message!(Errno::ENOENT, "the file does not exist");
     }
 
@@ -227,6 +250,12 @@ impl DirOps for RootDirOps {
             (f)(dir.this_weak().clone())
         });
 
+        if let Some(vmcore) = vmcore() {
+            cached_children.put_entry_if_not_found("vmcore", || {
+                VmcoreFileOps::new_inode(vmcore, dir.this_weak().clone())
+            });
+        }
+
         cached_children.downgrade()
     }
 }
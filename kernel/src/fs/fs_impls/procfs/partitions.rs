@@ -0,0 +1,67 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! This module offers `/proc/partitions` file support, which lists the disks and their
+//! partitions with the sizes in 1-KiB blocks.
+//!
+//! Reference: <https://elixir.bootlin.com/linux/v6.18/source/block/genhd.c>
+
+use aster_block::SECTOR_SIZE;
+use aster_util::printer::VmPrinter;
+
+use crate::{
+    fs::{
+        file::mkmod,
+        procfs::template::{FileOps, ProcFileBuilder},
+        vfs::inode::Inode,
+    },
+    prelude::*,
+};
+
+/// Represents the inode at `/proc/partitions`.
+pub struct PartitionsFileOps;
+
+impl PartitionsFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self, mkmod!(a+r))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for PartitionsFileOps {
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        let mut disks = aster_block::collect_all();
+        disks.retain(|device| !device.is_partition());
+        disks.sort_by_key(|disk| {
+            let id = disk.id();
+            (id.major().get(), id.minor().get())
+        });
+
+        writeln!(printer, "major minor  #blocks  name\n")?;
+        for disk in disks {
+            // Like Linux, disks without any capacity (e.g., unbound loop devices) are hidden.
+            if disk.metadata().nr_sectors == 0 {
+                continue;
+            }
+
+            let partitions = disk.partitions().unwrap_or_default();
+            for device in core::iter::once(disk).chain(partitions) {
+                let id = device.id();
+                let nr_blocks = device.metadata().nr_sectors * SECTOR_SIZE / 1024;
+                writeln!(
+                    printer,
+                    "{:4}  {:7} {:10} {}",
+                    id.major().get(),
+                    id.minor().get(),
+                    nr_blocks,
+                    device.name()
+                )?;
+            }
+        }
+
+        Ok(printer.bytes_written())
+    }
+}
@@ -0,0 +1,146 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_util::{printer::VmPrinter, slot_vec::SlotVec};
+use ostd::sync::RwMutexUpgradeableGuard;
+
+use super::TidDirOps;
+use crate::{
+    fs::{
+        file::mkmod,
+        procfs::template::{
+            DirOps, FileOps, ProcDir, ProcDirBuilder, ProcFileBuilder, lookup_child_from_table,
+            populate_children_from_table,
+        },
+        vfs::inode::Inode,
+    },
+    prelude::*,
+    process::posix_thread::AsPosixThread,
+    security::apparmor,
+    thread::Thread,
+};
+
+/// Represents the inode at `/proc/[pid]/task/[tid]/attr` (and also `/proc/[pid]/attr`).
+///
+/// The files in the directory expose the attributes of the security modules.
+pub(super) struct AttrDirOps(TidDirOps);
+
+impl AttrDirOps {
+    pub fn new_inode(dir: &TidDirOps, parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference: <https://elixir.bootlin.com/linux/v6.18/source/fs/proc/base.c#L3338>
+        ProcDirBuilder::new(Self(dir.clone()), mkmod!(a+rx))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+
+    #[expect(clippy::type_complexity)]
+    const STATIC_ENTRIES: &'static [(
+        &'static str,
+        fn(&TidDirOps, Weak<dyn Inode>) -> Arc<dyn Inode>,
+    )] = &[
+        ("current", CurrentFileOps::new_inode),
+        ("exec", ExecFileOps::new_inode),
+    ];
+}
+
+impl DirOps for AttrDirOps {
+    fn lookup_child(&self, dir: &ProcDir<Self>, name: &str) -> Result<Arc<dyn Inode>> {
+        let mut cached_children = dir.cached_children().write();
+
+        if let Some(child) =
+            lookup_child_from_table(name, &mut cached_children, Self::STATIC_ENTRIES, |f| {
+                (f)(&self.0, dir.this_weak().clone())
+            })
+        {
+            return Ok(child);
+        }
+
+        return_errno_with_message!(Errno::ENOENT, "the file does not exist");
+    }
+
+    fn populate_children<'a>(
+        &self,
+        dir: &'a ProcDir<Self>,
+    ) -> RwMutexUpgradeableGuard<'a, SlotVec<(String, Arc<dyn Inode>)>> {
+        let mut cached_children = dir.cached_children().write();
+
+        populate_children_from_table(&mut cached_children, Self::STATIC_ENTRIES, |f| {
+            (f)(&self.0, dir.this_weak().clone())
+        });
+
+        cached_children.downgrade()
+    }
+}
+
+/// Represents the inode at `/proc/[pid]/task/[tid]/attr/current`.
+///
+/// The file describes the AppArmor profile that confines the thread.
+struct CurrentFileOps(TidDirOps);
+
+impl CurrentFileOps {
+    pub fn new_inode(dir: &TidDirOps, parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference: <https://elixir.bootlin.com/linux/v6.18/source/fs/proc/base.c#L2828>
+        ProcFileBuilder::new(Self(dir.clone()), mkmod!(a+r))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for CurrentFileOps {
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        let thread = self.0.thread();
+        let posix_thread = thread.as_posix_thread().unwrap();
+        write!(printer, "{}", apparmor::current_attr(posix_thread))?;
+
+        Ok(printer.bytes_written())
+    }
+}
+
+/// Represents the inode at `/proc/[pid]/task/[tid]/attr/exec`.
+///
+/// The file describes the AppArmor profile to switch to on the next `execve` of the thread.
+/// Writing `exec NAME` to the file sets the profile, which is allowed only for the thread itself.
+struct ExecFileOps(TidDirOps);
+
+impl ExecFileOps {
+    pub fn new_inode(dir: &TidDirOps, parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference: <https://elixir.bootlin.com/linux/v6.18/source/fs/proc/base.c#L2832>
+        ProcFileBuilder::new(Self(dir.clone()), mkmod!(a+rw))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for ExecFileOps {
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        let thread = self.0.thread();
+        let posix_thread = thread.as_posix_thread().unwrap();
+        write!(printer, "{}", apparmor::exec_attr(posix_thread))?;
+
+        Ok(printer.bytes_written())
+    }
+
+    fn write_at(&self, _offset: usize, reader: &mut VmReader) -> Result<usize> {
+        let thread = self.0.thread();
+        if Thread::current().is_none_or(|current| !Arc::ptr_eq(&current, &thread)) {
+            return_errno_with_message!(
+                Errno::EACCES,
+                "the attributes of other threads cannot be changed"
+            );
+        }
+
+        let (command, len) = reader.read_cstring_until_end(PAGE_SIZE)?;
+        let command = command
+            .to_str()
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the command is not valid UTF-8"))?;
+        apparmor::set_exec_attr(thread.as_posix_thread().unwrap(), command)?;
+
+        Ok(len)
+    }
+}
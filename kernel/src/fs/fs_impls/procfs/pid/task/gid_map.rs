@@ -10,11 +10,10 @@ use crate::{
         vfs::inode::Inode,
     },
     prelude::*,
-    process::{Gid, Process},
+    process::Process,
 };
 
 /// Represents the inode at `/proc/[pid]/task/[tid]/gid_map` (and also `/proc/[pid]/gid_map`).
-#[expect(dead_code)]
 pub struct GidMapFileOps(Arc<Process>);
 
 impl GidMapFileOps {
@@ -32,16 +31,16 @@ impl FileOps for GidMapFileOps {
     fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
         let mut printer = VmPrinter::new_skip(writer, offset);
 
-        // This is the default GID map for the initial user namespace.
-        // TODO: Retrieve the GID map from the user namespace of the current process
-        // instead of returning this hard-coded value.
-        writeln!(
-            printer,
-            "{:>10} {:>10} {:>10}",
-            0,
-            0,
-            u32::from(Gid::INVALID)
-        )?;
+        let user_ns = self.0.user_ns().lock().clone();
+        for extent in user_ns.gid_map().extents() {
+            writeln!(
+                printer,
+                "{:>10} {:>10} {:>10}",
+                extent.first(),
+                extent.lower_first(),
+                extent.count()
+            )?;
+        }
 
         Ok(printer.bytes_written())
     }
@@ -10,11 +10,13 @@ use crate::{
         file::mkmod,
         procfs::{
             pid::task::{
-                cgroup::CgroupFileOps, cmdline::CmdlineFileOps, comm::CommFileOps,
-                environ::EnvironFileOps, exe::ExeSymOps, fd::FdDirOps, gid_map::GidMapFileOps,
-                maps::MapsFileOps, mem::MemFileOps, mountinfo::MountInfoFileOps,
-                mounts::MountsFileOps, ns::NsDirOps, oom_score_adj::OomScoreAdjFileOps,
-                stat::StatFileOps, status::StatusFileOps, uid_map::UidMapFileOps,
+                attr::AttrDirOps, cgroup::CgroupFileOps, cmdline::CmdlineFileOps,
+                comm::CommFileOps, environ::EnvironFileOps, exe::ExeSymOps, fd::FdDirOps,
+                gid_map::GidMapFileOps, maps::MapsFileOps, mem::MemFileOps,
+                mountinfo::MountInfoFileOps, mounts::MountsFileOps, ns::NsDirOps,
+                oom_score_adj::OomScoreAdjFileOps, sched::SchedFileOps,
+                schedstat::SchedStatFileOps, stat::StatFileOps, status::StatusFileOps,
+                uid_map::UidMapFileOps,
             },
             template::{
                 DirOps, ProcDir, ProcDirBuilder, lookup_child_from_table,
@@ -29,6 +31,7 @@ use crate::{
     thread::{AsThread, Thread, Tid},
 };
 
+mod attr;
 mod cgroup;
 mod cmdline;
 mod comm;
@@ -42,6 +45,8 @@ mod mountinfo;
 mod mounts;
 mod ns;
 mod oom_score_adj;
+mod sched;
+mod schedstat;
 mod stat;
 mod status;
 mod uid_map;
@@ -104,6 +109,7 @@ impl TidDirOps {
         &'static str,
         fn(&TidDirOps, Weak<dyn Inode>) -> Arc<dyn Inode>,
     )] = &[
+        ("attr", AttrDirOps::new_inode),
         ("cgroup", CgroupFileOps::new_inode),
         ("cmdline", CmdlineFileOps::new_inode),
         ("comm", CommFileOps::new_inode),
@@ -116,6 +122,8 @@ impl TidDirOps {
         ("mountinfo", MountInfoFileOps::new_inode),
         ("ns", NsDirOps::new_inode),
         ("oom_score_adj", OomScoreAdjFileOps::new_inode),
+        ("sched", SchedFileOps::new_inode),
+        ("schedstat", SchedStatFileOps::new_inode),
         ("stat", StatFileOps::new_inode),
         ("status", StatusFileOps::new_inode),
         ("uid_map", UidMapFileOps::new_inode),
@@ -56,6 +56,12 @@ struct MountInfoEntry<'a> {
     mount_point: &'a str,
     /// Per-mount flags.
     mount_flags: PerMountFlags,
+    /// The ID of the peer group if the mount is shared.
+    peer_group_id: Option<usize>,
+    /// The ID of the master peer group if the mount is a slave.
+    master_id: Option<usize>,
+    /// Whether the mount is unbindable.
+    is_unbindable: bool,
     /// The type of the filesystem in the form "type[.subtype]".
     fs_type: &'a str,
     /// Filesystem-specific information or "none".
@@ -68,7 +74,7 @@ impl core::fmt::Display for MountInfoEntry<'_> {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(
             f,
-            "{} {} {}:{} {} {} {} - {} {} {}",
+            "{} {} {}:{} {} {} {}",
             self.mount_id,
             self.parent_id,
             self.major,
@@ -76,9 +82,23 @@ impl core::fmt::Display for MountInfoEntry<'_> {
             &self.root,
             &self.mount_point,
             &self.mount_flags,
-            &self.fs_type,
-            &self.source,
-            &self.fs_flags,
+        )?;
+
+        // The optional fields.
+        if let Some(peer_group_id) = self.peer_group_id {
+            write!(f, " shared:{}", peer_group_id)?;
+        }
+        if let Some(master_id) = self.master_id {
+            write!(f, " master:{}", master_id)?;
+        }
+        if self.is_unbindable {
+            write!(f, " unbindable")?;
+        }
+
+        write!(
+            f,
+            " - {} {} {}",
+            &self.fs_type, &self.source, &self.fs_flags
         )
     }
 }
@@ -140,6 +160,9 @@ impl MountInfoFileOps {
                 root: &root,
                 mount_point: &mount_point,
                 mount_flags,
+                peer_group_id: mount.peer_group_id(),
+                master_id: mount.master_id(),
+                is_unbindable: mount.is_unbindable(),
                 fs_type,
                 source,
                 fs_flags,
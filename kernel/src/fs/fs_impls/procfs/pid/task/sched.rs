@@ -0,0 +1,80 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_util::printer::VmPrinter;
+
+use super::TidDirOps;
+use crate::{
+    fs::{
+        file::mkmod,
+        procfs::template::{FileOps, ProcFileBuilder},
+        vfs::inode::Inode,
+    },
+    prelude::*,
+    process::posix_thread::AsPosixThread,
+};
+
+/// Represents the inode at either `/proc/[pid]/sched` or `/proc/[pid]/task/[tid]/sched`.
+///
+/// The file shows the scheduler statistics of the thread in a human-readable format. The times
+/// are in milliseconds. See <https://elixir.bootlin.com/linux/v6.16.5/source/kernel/sched/debug.c>.
+pub struct SchedFileOps(TidDirOps);
+
+impl SchedFileOps {
+    pub fn new_inode(dir: &TidDirOps, parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/fs/proc/base.c>
+        ProcFileBuilder::new(Self(dir.clone()), mkmod!(a+r))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for SchedFileOps {
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        let thread = self.0.thread();
+        let posix_thread = thread.as_posix_thread().unwrap();
+        let stats = thread.sched_attr().stats();
+
+        writeln!(
+            printer,
+            "{} ({}, #threads: {})",
+            posix_thread.thread_name().lock().name().to_string_lossy(),
+            posix_thread.tid(),
+            self.0.process_ref.tasks().lock().as_slice().len()
+        )?;
+        writeln!(printer, "{}", "-".repeat(67))?;
+
+        let time_fields = [
+            ("se.sum_exec_runtime", stats.exec_runtime_ns()),
+            ("wait_max", stats.wait_max_ns()),
+            ("wait_sum", stats.run_delay_ns()),
+        ];
+        for (name, ns) in time_fields {
+            writeln!(
+                printer,
+                "{:<45}:{:>14}.{:06}",
+                name,
+                ns / 1_000_000,
+                ns % 1_000_000
+            )?;
+        }
+
+        let count_fields = [
+            ("se.nr_migrations", stats.nr_migrations()),
+            ("wait_count", stats.pcount()),
+            (
+                "nr_switches",
+                stats.nr_voluntary_switches() + stats.nr_involuntary_switches(),
+            ),
+            ("nr_voluntary_switches", stats.nr_voluntary_switches()),
+            ("nr_involuntary_switches", stats.nr_involuntary_switches()),
+        ];
+        for (name, count) in count_fields {
+            writeln!(printer, "{:<45}:{:>21}", name, count)?;
+        }
+
+        Ok(printer.bytes_written())
+    }
+}
@@ -0,0 +1,49 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_util::printer::VmPrinter;
+
+use super::TidDirOps;
+use crate::{
+    fs::{
+        file::mkmod,
+        procfs::template::{FileOps, ProcFileBuilder},
+        vfs::inode::Inode,
+    },
+    prelude::*,
+};
+
+/// Represents the inode at either `/proc/[pid]/schedstat` or `/proc/[pid]/task/[tid]/schedstat`.
+///
+/// Fields:
+/// - exec_runtime : Time spent running on CPUs (nanoseconds).
+/// - run_delay    : Time spent waiting in run queues (nanoseconds).
+/// - pcount       : Number of timeslices run on CPUs.
+pub struct SchedStatFileOps(TidDirOps);
+
+impl SchedStatFileOps {
+    pub fn new_inode(dir: &TidDirOps, parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/fs/proc/base.c>
+        ProcFileBuilder::new(Self(dir.clone()), mkmod!(a+r))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for SchedStatFileOps {
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        let thread = self.0.thread();
+        let stats = thread.sched_attr().stats();
+        writeln!(
+            printer,
+            "{} {} {}",
+            stats.exec_runtime_ns(),
+            stats.run_delay_ns(),
+            stats.pcount()
+        )?;
+
+        Ok(printer.bytes_written())
+    }
+}
@@ -181,6 +181,18 @@ impl FileOps for StatusFileOps {
         writeln!(printer, "CapBnd:\t{:016x}", BOUNDING_CAPSET.bits())?;
         writeln!(printer, "CapAmb:\t{:016x}", AMBIENT_CAPSET.bits())?;
 
+        let sched_stats = thread.sched_attr().stats();
+        writeln!(
+            printer,
+            "voluntary_ctxt_switches:\t{}",
+            sched_stats.nr_voluntary_switches()
+        )?;
+        writeln!(
+            printer,
+            "nonvoluntary_ctxt_switches:\t{}",
+            sched_stats.nr_involuntary_switches()
+        )?;
+
         Ok(printer.bytes_written())
     }
 }
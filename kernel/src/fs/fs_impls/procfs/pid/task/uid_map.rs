@@ -14,7 +14,6 @@ use crate::{
 };
 
 /// Represents the inode at `/proc/[pid]/task/[tid]/uid_map` (and also `/proc/[pid]/uid_map`).
-#[expect(dead_code)]
 pub struct UidMapFileOps(Arc<Process>);
 
 impl UidMapFileOps {
@@ -32,16 +31,16 @@ impl FileOps for UidMapFileOps {
     fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
         let mut printer = VmPrinter::new_skip(writer, offset);
 
-        // This is the default UID map for the initial user namespace.
-        // TODO: Retrieve the UID map from the user namespace of the current process
-        // instead of returning this hard-coded value.
-        writeln!(
-            printer,
-            "{:>10} {:>10} {:>10}",
-            0,
-            0,
-            u32::from(Uid::INVALID)
-        )?;
+        let user_ns = self.0.user_ns().lock().clone();
+        for extent in user_ns.uid_map().extents() {
+            writeln!(
+                printer,
+                "{:>10} {:>10} {:>10}",
+                extent.first(),
+                extent.lower_first(),
+                extent.count()
+            )?;
+        }
 
         Ok(printer.bytes_written())
     }
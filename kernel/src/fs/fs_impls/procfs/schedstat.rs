@@ -0,0 +1,112 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! This module offers `/proc/schedstat` file support, which provides the
+//! scheduler statistics of every CPU.
+//!
+//! Reference: <https://docs.kernel.org/scheduler/sched-stats.html>
+
+use aster_util::printer::VmPrinter;
+use ostd::{
+    cpu::{CpuSet, all_cpus, num_cpus},
+    timer::Jiffies,
+    util::id_set::Id,
+};
+
+use crate::{
+    cpu::hotplug,
+    fs::{
+        file::mkmod,
+        procfs::template::{FileOps, ProcFileBuilder},
+        vfs::inode::Inode,
+    },
+    prelude::*,
+    sched::{CpuIdleType, cpu_sched_stats},
+};
+
+/// The version of the format.
+const SCHEDSTAT_VERSION: u32 = 15;
+
+/// Represents the inode at `/proc/schedstat`.
+pub struct SchedStatFileOps;
+
+impl SchedStatFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference:
+        // <https://elixir.bootlin.com/linux/v6.16.5/source/kernel/sched/stats.c>
+        // <https://elixir.bootlin.com/linux/v6.16.5/source/fs/proc/generic.c#L549-L550>
+        ProcFileBuilder::new(Self, mkmod!(a+r))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for SchedStatFileOps {
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        writeln!(printer, "version {}", SCHEDSTAT_VERSION)?;
+        writeln!(printer, "timestamp {}", Jiffies::elapsed().as_u64())?;
+
+        // All the online CPUs are balanced as a single scheduling domain.
+        let online_cpus = hotplug::online_cpus();
+        for cpu in all_cpus().filter(|cpu| online_cpus.contains(*cpu)) {
+            let stats = cpu_sched_stats(cpu);
+
+            // The second field is the legacy `sched_yield` counter of the expired queue,
+            // which is always zero.
+            writeln!(
+                printer,
+                "cpu{} {} 0 {} {} {} {} {} {} {}",
+                cpu.as_usize(),
+                stats.yld_count,
+                stats.sched_count,
+                stats.sched_goidle,
+                stats.ttwu_count,
+                stats.ttwu_local,
+                stats.rq_cpu_time_ns(),
+                stats.run_delay_ns(),
+                stats.pcount,
+            )?;
+
+            write!(printer, "domain0 ")?;
+            write_cpu_mask(&mut printer, &online_cpus)?;
+            for idle_type in CpuIdleType::ALL {
+                let lb = stats.lb(idle_type);
+                // There are no groups in the domain, so `lb_hot_gained` and `lb_nobusyg` are
+                // always zero.
+                write!(
+                    printer,
+                    " {} {} {} {} {} 0 {} 0",
+                    lb.count, lb.balanced, lb.failed, lb.imbalance, lb.gained, lb.no_busy_queue,
+                )?;
+            }
+            // Active balancing and balancing on exec or fork are not supported, and a woken task
+            // is never moved for balancing other than to its waker's CPU.
+            writeln!(
+                printer,
+                " 0 0 0 0 0 0 0 0 0 {} {} 0",
+                stats.ttwu_wake_remote, stats.ttwu_move_affine,
+            )?;
+        }
+
+        Ok(printer.bytes_written())
+    }
+}
+
+/// Writes the CPU mask in hexadecimal, with a comma between every 32 bits.
+fn write_cpu_mask(printer: &mut VmPrinter, cpus: &CpuSet) -> Result<()> {
+    let mut digits = vec![0u8; num_cpus().div_ceil(4)];
+    for cpu in cpus.iter() {
+        digits[cpu.as_usize() / 4] |= 1 << (cpu.as_usize() % 4);
+    }
+
+    for (i, digit) in digits.iter().enumerate().rev() {
+        write!(printer, "{:x}", digit)?;
+        if i != 0 && i % 8 == 0 {
+            write!(printer, ",")?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,69 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! This module offers `/proc/slabinfo` file support, which provides the
+//! statistics of the slab caches of the kernel heap.
+//!
+//! Reference: <https://man7.org/linux/man-pages/man5/slabinfo.5.html>
+
+use aster_util::printer::VmPrinter;
+use osdk_heap_allocator::slab_cache_stats;
+
+use crate::{
+    fs::{
+        file::mkmod,
+        procfs::template::{FileOps, ProcFileBuilder},
+        vfs::inode::Inode,
+    },
+    prelude::*,
+};
+
+/// Represents the inode at `/proc/slabinfo`.
+pub struct SlabInfoFileOps;
+
+impl SlabInfoFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference:
+        // <https://elixir.bootlin.com/linux/v6.16.5/source/mm/slab_common.c#L1175>
+        ProcFileBuilder::new(Self, mkmod!(u+r))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for SlabInfoFileOps {
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        writeln!(printer, "slabinfo - version: 2.1")?;
+        writeln!(
+            printer,
+            "# name            <active_objs> <num_objs> <objsize> <objperslab> <pagesperslab> \
+             : tunables <limit> <batchcount> <sharedfactor> \
+             : slabdata <active_slabs> <num_slabs> <sharedavail>"
+        )?;
+
+        // Each slab is a single page. The per-CPU magazines are reported as
+        // the tunables, and the slots in the shared depot as `sharedavail`.
+        for stats in slab_cache_stats().iter().rev() {
+            writeln!(
+                printer,
+                "kmalloc-{:<9} {:>6} {:>6} {:>6} {:>4} {:>4} : tunables {:>4} {:>4} {:>4} : slabdata {:>6} {:>6} {:>6}",
+                stats.slot_size,
+                stats.nr_active_slots,
+                stats.nr_slots,
+                stats.slot_size,
+                stats.slots_per_slab,
+                1,
+                stats.magazine_capacity,
+                stats.magazine_capacity,
+                0,
+                stats.nr_active_slabs,
+                stats.nr_slabs,
+                stats.nr_depot_slots,
+            )?;
+        }
+
+        Ok(printer.bytes_written())
+    }
+}
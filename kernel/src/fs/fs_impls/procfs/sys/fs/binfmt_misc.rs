@@ -0,0 +1,223 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_util::{printer::VmPrinter, slot_vec::SlotVec};
+use ostd::sync::RwMutexUpgradeableGuard;
+
+use crate::{
+    fs::{
+        file::mkmod,
+        procfs::template::{
+            DirOps, FileOps, ProcDir, ProcDirBuilder, ProcFile, ProcFileBuilder,
+            lookup_child_from_table, populate_children_from_table,
+        },
+        utils::DirEntryVecExt,
+        vfs::inode::Inode,
+    },
+    prelude::*,
+    process::binfmt_misc::{self, BinfmtCommand, BinfmtEntry, MAX_REGISTER_LEN},
+};
+
+/// Represents the inode at `/proc/sys/fs/binfmt_misc`.
+///
+/// Besides the `register` and `status` files, the directory contains a file for each
+/// registered entry.
+pub struct BinfmtMiscDirOps;
+
+impl BinfmtMiscDirOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/fs/binfmt_misc.c#L876>
+        ProcDirBuilder::new(Self, mkmod!(a+rx, u+w))
+            .parent(parent)
+            .volatile()
+            .build()
+            .unwrap()
+    }
+
+    #[expect(clippy::type_complexity)]
+    const STATIC_ENTRIES: &'static [(&'static str, fn(Weak<dyn Inode>) -> Arc<dyn Inode>)] = &[
+        ("register", RegisterFileOps::new_inode),
+        ("status", StatusFileOps::new_inode),
+    ];
+}
+
+impl DirOps for BinfmtMiscDirOps {
+    fn lookup_child(&self, dir: &ProcDir<Self>, name: &str) -> Result<Arc<dyn Inode>> {
+        let mut cached_children = dir.cached_children().write();
+
+        if let Some(child) =
+            lookup_child_from_table(name, &mut cached_children, Self::STATIC_ENTRIES, |f| {
+                (f)(dir.this_weak().clone())
+            })
+        {
+            return Ok(child);
+        }
+
+        let Some(entry) = binfmt_misc::lookup(name) else {
+            return_errno_with_message!(Errno::ENOENT, "the file does not exist");
+        };
+
+        let child = EntryFileOps::new_inode(entry, dir.this_weak().clone());
+        // The old entry (if any) is outdated given that `lookup_child` is called.
+        cached_children.remove_entry_by_name(name);
+        cached_children.put((String::from(name), child.clone()));
+
+        Ok(child)
+    }
+
+    fn populate_children<'a>(
+        &self,
+        dir: &'a ProcDir<Self>,
+    ) -> RwMutexUpgradeableGuard<'a, SlotVec<(String, Arc<dyn Inode>)>> {
+        let mut cached_children = dir.cached_children().write();
+
+        // Remove outdated entries.
+        for i in 0..cached_children.slots_len() {
+            let Some((_, child)) = cached_children.get(i) else {
+                continue;
+            };
+            if !self.validate_child(child.as_ref()) {
+                cached_children.remove(i);
+            }
+        }
+
+        // Add new entries.
+        populate_children_from_table(&mut cached_children, Self::STATIC_ENTRIES, |f| {
+            (f)(dir.this_weak().clone())
+        });
+        for entry in binfmt_misc::entries() {
+            cached_children.put_entry_if_not_found(entry.name(), || {
+                EntryFileOps::new_inode(entry.clone(), dir.this_weak().clone())
+            });
+        }
+
+        cached_children.downgrade()
+    }
+
+    fn validate_child(&self, child: &dyn Inode) -> bool {
+        let Some(child) = child.downcast_ref::<ProcFile<EntryFileOps>>() else {
+            // The static entries are always valid.
+            return true;
+        };
+
+        let entry = &child.inner().0;
+        binfmt_misc::lookup(entry.name()).is_some_and(|current| Arc::ptr_eq(&current, entry))
+    }
+}
+
+/// Represents the inode at `/proc/sys/fs/binfmt_misc/register`.
+struct RegisterFileOps;
+
+impl RegisterFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/fs/binfmt_misc.c#L993>
+        ProcFileBuilder::new(Self, mkmod!(u+w))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for RegisterFileOps {
+    fn read_at(&self, _offset: usize, _writer: &mut VmWriter) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "the register file cannot be read");
+    }
+
+    fn write_at(&self, _offset: usize, reader: &mut VmReader) -> Result<usize> {
+        if reader.remain() > MAX_REGISTER_LEN {
+            return_errno_with_message!(Errno::EINVAL, "the registration string is too long");
+        }
+
+        let (line, read_bytes) = reader.read_cstring_until_end(MAX_REGISTER_LEN)?;
+        binfmt_misc::register(line.to_str()?)?;
+
+        Ok(read_bytes)
+    }
+}
+
+/// Represents the inode at `/proc/sys/fs/binfmt_misc/status`.
+struct StatusFileOps;
+
+impl StatusFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/fs/binfmt_misc.c#L1000>
+        ProcFileBuilder::new(Self, mkmod!(a+r, u+w))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for StatusFileOps {
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        if binfmt_misc::is_enabled() {
+            writeln!(printer, "enabled")?;
+        } else {
+            writeln!(printer, "disabled")?;
+        }
+
+        Ok(printer.bytes_written())
+    }
+
+    fn write_at(&self, _offset: usize, reader: &mut VmReader) -> Result<usize> {
+        let (command, read_bytes) = read_command_from(reader)?;
+
+        match command {
+            BinfmtCommand::Disable => binfmt_misc::set_enabled(false),
+            BinfmtCommand::Enable => binfmt_misc::set_enabled(true),
+            BinfmtCommand::Remove => binfmt_misc::unregister_all(),
+        }
+
+        Ok(read_bytes)
+    }
+}
+
+/// Represents the inode at `/proc/sys/fs/binfmt_misc/[name]`.
+struct EntryFileOps(Arc<BinfmtEntry>);
+
+impl EntryFileOps {
+    pub fn new_inode(entry: Arc<BinfmtEntry>, parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/fs/binfmt_misc.c#L835>
+        ProcFileBuilder::new(Self(entry), mkmod!(a+r, u+w))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for EntryFileOps {
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        write!(printer, "{}", self.0)?;
+
+        Ok(printer.bytes_written())
+    }
+
+    fn write_at(&self, _offset: usize, reader: &mut VmReader) -> Result<usize> {
+        let (command, read_bytes) = read_command_from(reader)?;
+
+        match command {
+            BinfmtCommand::Disable => self.0.set_enabled(false),
+            BinfmtCommand::Enable => self.0.set_enabled(true),
+            BinfmtCommand::Remove => binfmt_misc::unregister(self.0.name())?,
+        }
+
+        Ok(read_bytes)
+    }
+}
+
+fn read_command_from(reader: &mut VmReader) -> Result<(BinfmtCommand, usize)> {
+    /// The longest possible command is `"-1\n"`.
+    const MAX_COMMAND_LEN: usize = 3;
+
+    if reader.remain() > MAX_COMMAND_LEN {
+        return_errno_with_message!(Errno::EINVAL, "the binfmt_misc command is too long");
+    }
+
+    let (command, read_bytes) = reader.read_cstring_until_end(MAX_COMMAND_LEN)?;
+    let command = BinfmtCommand::parse(command.to_str()?)?;
+
+    Ok((command, read_bytes))
+}
@@ -9,7 +9,15 @@ use crate::{
         procfs::{
             ProcDir,
             sys::kernel::{
-                cap_last_cap::CapLastCapFileOps, pid_max::PidMaxFileOps, yama::YamaDirOps,
+                cap_last_cap::CapLastCapFileOps,
+                oops_limit::OopsLimitFileOps,
+                panic::PanicFileOps,
+                panic_on_oops::PanicOnOopsFileOps,
+                pid_max::PidMaxFileOps,
+                printk::PrintkFileOps,
+                printk_ratelimit::{PrintkRatelimitBurstFileOps, PrintkRatelimitFileOps},
+                random::RandomDirOps,
+                yama::YamaDirOps,
             },
             template::{
                 DirOps, ProcDirBuilder, lookup_child_from_table, populate_children_from_table,
@@ -21,7 +29,13 @@ use crate::{
 };
 
 mod cap_last_cap;
+mod oops_limit;
+mod panic;
+mod panic_on_oops;
 mod pid_max;
+mod printk;
+mod printk_ratelimit;
+mod random;
 mod yama;
 
 /// Represents the inode at `/proc/sys/kernel`.
@@ -41,7 +55,17 @@ impl KernelDirOps {
     #[expect(clippy::type_complexity)]
     const STATIC_ENTRIES: &'static [(&'static str, fn(Weak<dyn Inode>) -> Arc<dyn Inode>)] = &[
         ("cap_last_cap", CapLastCapFileOps::new_inode),
+        ("oops_limit", OopsLimitFileOps::new_inode),
+        ("panic", PanicFileOps::new_inode),
+        ("panic_on_oops", PanicOnOopsFileOps::new_inode),
         ("pid_max", PidMaxFileOps::new_inode),
+        ("printk", PrintkFileOps::new_inode),
+        ("printk_ratelimit", PrintkRatelimitFileOps::new_inode),
+        (
+            "printk_ratelimit_burst",
+            PrintkRatelimitBurstFileOps::new_inode,
+        ),
+        ("random", RandomDirOps::new_inode),
         ("yama", YamaDirOps::new_inode),
     ];
 }
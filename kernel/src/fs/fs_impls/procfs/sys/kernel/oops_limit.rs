@@ -0,0 +1,46 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_util::printer::VmPrinter;
+
+use crate::{
+    fs::{
+        file::mkmod,
+        procfs::template::{FileOps, ProcFileBuilder, read_i32_from},
+        vfs::inode::Inode,
+    },
+    prelude::*,
+    thread::oops::{oops_limit, set_oops_limit},
+};
+
+/// Represents the inode at `/proc/sys/kernel/oops_limit`.
+pub struct OopsLimitFileOps;
+
+impl OopsLimitFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/kernel/exit.c>
+        ProcFileBuilder::new(Self, mkmod!(a+r, u+w))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for OopsLimitFileOps {
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        writeln!(printer, "{}", oops_limit())?;
+
+        Ok(printer.bytes_written())
+    }
+
+    fn write_at(&self, _offset: usize, reader: &mut VmReader) -> Result<usize> {
+        let (val, read_bytes) = read_i32_from(reader)?;
+        let limit = u32::try_from(val)
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the limit cannot be negative"))?;
+
+        set_oops_limit(limit);
+
+        Ok(read_bytes)
+    }
+}
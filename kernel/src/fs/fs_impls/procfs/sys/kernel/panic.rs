@@ -0,0 +1,44 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_util::printer::VmPrinter;
+
+use crate::{
+    fs::{
+        file::mkmod,
+        procfs::template::{FileOps, ProcFileBuilder, read_i32_from},
+        vfs::inode::Inode,
+    },
+    prelude::*,
+    thread::oops::{panic_timeout, set_panic_timeout},
+};
+
+/// Represents the inode at `/proc/sys/kernel/panic`.
+pub struct PanicFileOps;
+
+impl PanicFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/kernel/panic.c>
+        ProcFileBuilder::new(Self, mkmod!(a+r, u+w))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for PanicFileOps {
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        writeln!(printer, "{}", panic_timeout())?;
+
+        Ok(printer.bytes_written())
+    }
+
+    fn write_at(&self, _offset: usize, reader: &mut VmReader) -> Result<usize> {
+        let (val, read_bytes) = read_i32_from(reader)?;
+
+        set_panic_timeout(val);
+
+        Ok(read_bytes)
+    }
+}
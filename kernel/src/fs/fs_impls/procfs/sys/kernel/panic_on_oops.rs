@@ -0,0 +1,45 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_util::printer::VmPrinter;
+
+use crate::{
+    fs::{
+        file::mkmod,
+        procfs::template::{FileOps, ProcFileBuilder, read_i32_from},
+        vfs::inode::Inode,
+    },
+    prelude::*,
+    thread::oops::{panic_on_oops, set_panic_on_oops},
+};
+
+/// Represents the inode at `/proc/sys/kernel/panic_on_oops`.
+pub struct PanicOnOopsFileOps;
+
+impl PanicOnOopsFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/kernel/panic.c>
+        ProcFileBuilder::new(Self, mkmod!(a+r, u+w))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for PanicOnOopsFileOps {
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        writeln!(printer, "{}", panic_on_oops() as i32)?;
+
+        Ok(printer.bytes_written())
+    }
+
+    fn write_at(&self, _offset: usize, reader: &mut VmReader) -> Result<usize> {
+        let (val, read_bytes) = read_i32_from(reader)?;
+
+        // Like Linux, any non-zero value enables panicking on oops.
+        set_panic_on_oops(val != 0);
+
+        Ok(read_bytes)
+    }
+}
@@ -0,0 +1,86 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_logger::console_level::{
+    console_loglevel, default_console_loglevel, default_message_loglevel, minimum_console_loglevel,
+    set_console_loglevel, set_default_console_loglevel, set_default_message_loglevel,
+    set_minimum_console_loglevel,
+};
+use aster_util::printer::VmPrinter;
+
+use crate::{
+    fs::{
+        file::mkmod,
+        procfs::template::{FileOps, ProcFileBuilder},
+        vfs::inode::Inode,
+    },
+    prelude::*,
+};
+
+/// Represents the inode at `/proc/sys/kernel/printk`.
+///
+/// The file contains the console log level, the default message log level, the minimum console
+/// log level, and the default console log level.
+pub struct PrintkFileOps;
+
+impl PrintkFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/kernel/printk/sysctl.c>
+        ProcFileBuilder::new(Self, mkmod!(a+r, u+w))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for PrintkFileOps {
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        writeln!(
+            printer,
+            "{}\t{}\t{}\t{}",
+            console_loglevel(),
+            default_message_loglevel(),
+            minimum_console_loglevel(),
+            default_console_loglevel()
+        )?;
+
+        Ok(printer.bytes_written())
+    }
+
+    fn write_at(&self, _offset: usize, reader: &mut VmReader) -> Result<usize> {
+        /// The maximum length of the written levels.
+        const MAX_LEVELS_LEN: usize = 64;
+
+        let (cstr, read_bytes) = reader.read_cstring_until_end(MAX_LEVELS_LEN)?;
+        let text = cstr
+            .to_str()
+            .map_err(|_| Error::with_message(Errno::EINVAL, "non-UTF8 log levels"))?;
+
+        let levels = text
+            .split_whitespace()
+            .map(|level| {
+                level
+                    .parse::<u8>()
+                    .map_err(|_| Error::with_message(Errno::EINVAL, "invalid log level"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if levels.is_empty() {
+            return_errno_with_message!(Errno::EINVAL, "no log levels are written");
+        }
+
+        // Like Linux, the levels that are not written are left unchanged, and extra levels are
+        // ignored.
+        let setters: [fn(u8); 4] = [
+            set_console_loglevel,
+            set_default_message_loglevel,
+            set_minimum_console_loglevel,
+            set_default_console_loglevel,
+        ];
+        for (setter, level) in setters.iter().zip(levels) {
+            setter(level);
+        }
+
+        Ok(read_bytes)
+    }
+}
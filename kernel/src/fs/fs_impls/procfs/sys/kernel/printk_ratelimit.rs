@@ -0,0 +1,85 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_logger::{
+    ratelimit_burst, ratelimit_interval_secs, set_ratelimit_burst, set_ratelimit_interval_secs,
+};
+use aster_util::printer::VmPrinter;
+
+use crate::{
+    fs::{
+        file::mkmod,
+        procfs::template::{FileOps, ProcFileBuilder, read_i32_from},
+        vfs::inode::Inode,
+    },
+    prelude::*,
+};
+
+/// Represents the inode at `/proc/sys/kernel/printk_ratelimit`.
+///
+/// The file contains the length of a rate-limiting interval in seconds.
+pub struct PrintkRatelimitFileOps;
+
+impl PrintkRatelimitFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/kernel/printk/sysctl.c>
+        ProcFileBuilder::new(Self, mkmod!(a+r, u+w))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for PrintkRatelimitFileOps {
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        writeln!(printer, "{}", ratelimit_interval_secs())?;
+
+        Ok(printer.bytes_written())
+    }
+
+    fn write_at(&self, _offset: usize, reader: &mut VmReader) -> Result<usize> {
+        let (val, read_bytes) = read_i32_from(reader)?;
+        let secs = u32::try_from(val)
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the interval cannot be negative"))?;
+
+        set_ratelimit_interval_secs(secs);
+
+        Ok(read_bytes)
+    }
+}
+
+/// Represents the inode at `/proc/sys/kernel/printk_ratelimit_burst`.
+///
+/// The file contains the maximum number of messages in a rate-limiting interval.
+pub struct PrintkRatelimitBurstFileOps;
+
+impl PrintkRatelimitBurstFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/kernel/printk/sysctl.c>
+        ProcFileBuilder::new(Self, mkmod!(a+r, u+w))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for PrintkRatelimitBurstFileOps {
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        writeln!(printer, "{}", ratelimit_burst())?;
+
+        Ok(printer.bytes_written())
+    }
+
+    fn write_at(&self, _offset: usize, reader: &mut VmReader) -> Result<usize> {
+        let (val, read_bytes) = read_i32_from(reader)?;
+        let burst = u32::try_from(val)
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the burst cannot be negative"))?;
+
+        set_ratelimit_burst(burst);
+
+        Ok(read_bytes)
+    }
+}
@@ -0,0 +1,246 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_util::{printer::VmPrinter, slot_vec::SlotVec};
+use ostd::sync::RwMutexUpgradeableGuard;
+use spin::Once;
+
+use crate::{
+    fs::{
+        file::mkmod,
+        procfs::template::{
+            DirOps, FileOps, ProcDir, ProcDirBuilder, ProcFileBuilder, lookup_child_from_table,
+            populate_children_from_table, read_i32_from,
+        },
+        vfs::inode::Inode,
+    },
+    prelude::*,
+    util::random::{self, POOL_SIZE_BITS},
+};
+
+/// Represents the inode at `/proc/sys/kernel/random`.
+pub struct RandomDirOps;
+
+impl RandomDirOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference:
+        // <https://elixir.bootlin.com/linux/v6.18/source/drivers/char/random.c>
+        // <https://elixir.bootlin.com/linux/v6.18/source/fs/proc/proc_sysctl.c>
+        ProcDirBuilder::new(Self, mkmod!(a+rx))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+
+    #[expect(clippy::type_complexity)]
+    const STATIC_ENTRIES: &'static [(&'static str, fn(Weak<dyn Inode>) -> Arc<dyn Inode>)] = &[
+        ("boot_id", UuidFileOps::new_boot_id_inode),
+        ("entropy_avail", EntropyAvailFileOps::new_inode),
+        ("poolsize", PoolSizeFileOps::new_inode),
+        (
+            "urandom_min_reseed_secs",
+            UrandomMinReseedSecsFileOps::new_inode,
+        ),
+        ("uuid", UuidFileOps::new_uuid_inode),
+        (
+            "write_wakeup_threshold",
+            WriteWakeupThresholdFileOps::new_inode,
+        ),
+    ];
+}
+
+impl DirOps for RandomDirOps {
+    fn lookup_child(&self, dir: &ProcDir<Self>, name: &str) -> Result<Arc<dyn Inode>> {
+        let mut cached_children = dir.cached_children().write();
+
+        if let Some(child) =
+            lookup_child_from_table(name, &mut cached_children, Self::STATIC_ENTRIES, |f| {
+                (f)(dir.this_weak().clone())
+            })
+        {
+            return Ok(child);
+        }
+
+        return_errno_with_message!(Errno::ENOENT, "the file does not exist");
+    }
+
+    fn populate_children<'a>(
+        &self,
+        dir: &'a ProcDir<Self>,
+    ) -> RwMutexUpgradeableGuard<'a, SlotVec<(String, Arc<dyn Inode>)>> {
+        let mut cached_children = dir.cached_children().write();
+
+        populate_children_from_table(&mut cached_children, Self::STATIC_ENTRIES, |f| {
+            (f)(dir.this_weak().clone())
+        });
+
+        cached_children.downgrade()
+    }
+}
+
+/// Represents the inode at `/proc/sys/kernel/random/boot_id` or `/proc/sys/kernel/random/uuid`.
+///
+/// The boot ID is generated once per boot, while a new UUID is generated on each read.
+struct UuidFileOps {
+    is_boot_id: bool,
+}
+
+impl UuidFileOps {
+    fn new_boot_id_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self { is_boot_id: true }, mkmod!(a+r))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+
+    fn new_uuid_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self { is_boot_id: false }, mkmod!(a+r))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for UuidFileOps {
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        static BOOT_ID: Once<[u8; 16]> = Once::new();
+
+        let uuid = if self.is_boot_id {
+            *BOOT_ID.call_once(generate_uuid)
+        } else {
+            generate_uuid()
+        };
+
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        for (i, byte) in uuid.iter().enumerate() {
+            if matches!(i, 4 | 6 | 8 | 10) {
+                write!(printer, "-")?;
+            }
+            write!(printer, "{:02x}", byte)?;
+        }
+        writeln!(printer)?;
+
+        Ok(printer.bytes_written())
+    }
+}
+
+/// Generates a random (version 4) UUID.
+///
+/// Reference: <https://datatracker.ietf.org/doc/html/rfc9562#section-5.4>.
+fn generate_uuid() -> [u8; 16] {
+    let mut uuid = [0u8; 16];
+    random::getrandom(&mut uuid);
+
+    uuid[6] = (uuid[6] & 0x0f) | 0x40;
+    uuid[8] = (uuid[8] & 0x3f) | 0x80;
+
+    uuid
+}
+
+/// Represents the inode at `/proc/sys/kernel/random/entropy_avail`.
+struct EntropyAvailFileOps;
+
+impl EntropyAvailFileOps {
+    fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self, mkmod!(a+r))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for EntropyAvailFileOps {
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        writeln!(printer, "{}", random::entropy_avail())?;
+
+        Ok(printer.bytes_written())
+    }
+}
+
+/// Represents the inode at `/proc/sys/kernel/random/poolsize`.
+struct PoolSizeFileOps;
+
+impl PoolSizeFileOps {
+    fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self, mkmod!(a+r))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for PoolSizeFileOps {
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        writeln!(printer, "{}", POOL_SIZE_BITS)?;
+
+        Ok(printer.bytes_written())
+    }
+}
+
+/// Represents the inode at `/proc/sys/kernel/random/urandom_min_reseed_secs`.
+struct UrandomMinReseedSecsFileOps;
+
+impl UrandomMinReseedSecsFileOps {
+    fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self, mkmod!(a+r, u+w))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for UrandomMinReseedSecsFileOps {
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        writeln!(printer, "{}", random::min_reseed_secs())?;
+
+        Ok(printer.bytes_written())
+    }
+
+    fn write_at(&self, _offset: usize, reader: &mut VmReader) -> Result<usize> {
+        let (val, read_bytes) = read_i32_from(reader)?;
+        let secs = u32::try_from(val)
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the interval cannot be negative"))?;
+
+        random::set_min_reseed_secs(secs);
+
+        Ok(read_bytes)
+    }
+}
+
+/// Represents the inode at `/proc/sys/kernel/random/write_wakeup_threshold`.
+struct WriteWakeupThresholdFileOps;
+
+impl WriteWakeupThresholdFileOps {
+    fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self, mkmod!(a+r, u+w))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for WriteWakeupThresholdFileOps {
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        writeln!(printer, "{}", random::write_wakeup_threshold())?;
+
+        Ok(printer.bytes_written())
+    }
+
+    fn write_at(&self, _offset: usize, reader: &mut VmReader) -> Result<usize> {
+        let (val, read_bytes) = read_i32_from(reader)?;
+        let bits = u32::try_from(val)
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the threshold cannot be negative"))?;
+
+        random::set_write_wakeup_threshold(bits);
+
+        Ok(read_bytes)
+    }
+}
@@ -3,7 +3,7 @@
 use aster_util::slot_vec::SlotVec;
 use ostd::sync::RwMutexUpgradeableGuard;
 
-use self::kernel::KernelDirOps;
+use self::{fs::FsDirOps, kernel::KernelDirOps, net::NetDirOps, vm::VmDirOps};
 use super::template::populate_children_from_table;
 use crate::{
     fs::{
@@ -14,7 +14,10 @@ use crate::{
     prelude::*,
 };
 
+mod fs;
 mod kernel;
+mod net;
+mod vm;
 
 /// Represents the inode at `/proc/sys`.
 pub struct SysDirOps;
@@ -31,8 +34,12 @@ impl SysDirOps {
     }
 
     #[expect(clippy::type_complexity)]
-    const STATIC_ENTRIES: &'static [(&'static str, fn(Weak<dyn Inode>) -> Arc<dyn Inode>)] =
-        &[("kernel", KernelDirOps::new_inode)];
+    const STATIC_ENTRIES: &'static [(&'static str, fn(Weak<dyn Inode>) -> Arc<dyn Inode>)] = &[
+        ("fs", FsDirOps::new_inode),
+        ("kernel", KernelDirOps::new_inode),
+        ("net", NetDirOps::new_inode),
+        ("vm", VmDirOps::new_inode),
+    ];
 }
 
 impl DirOps for SysDirOps {
@@ -0,0 +1,198 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_util::{printer::VmPrinter, slot_vec::SlotVec};
+use ostd::sync::RwMutexUpgradeableGuard;
+
+use crate::{
+    fs::{
+        file::mkmod,
+        procfs::template::{
+            DirOps, FileOps, ProcDir, ProcDirBuilder, ProcFileBuilder, lookup_child_from_table,
+            populate_children_from_table,
+        },
+        vfs::inode::Inode,
+    },
+    net::socket::ip::{raw_options::PingGroupRange, stream_options::CongestionControl},
+    prelude::*,
+};
+
+/// Represents the inode at `/proc/sys/net/ipv4`.
+pub struct Ipv4DirOps;
+
+impl Ipv4DirOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference:
+        // <https://elixir.bootlin.com/linux/v6.16.5/source/net/ipv4/sysctl_net_ipv4.c#L1592>
+        // <https://elixir.bootlin.com/linux/v6.16.5/source/fs/proc/proc_sysctl.c#L978>
+        ProcDirBuilder::new(Self, mkmod!(a+rx))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+
+    #[expect(clippy::type_complexity)]
+    const STATIC_ENTRIES: &'static [(&'static str, fn(Weak<dyn Inode>) -> Arc<dyn Inode>)] = &[
+        ("ping_group_range", PingGroupRangeFileOps::new_inode),
+        (
+            "tcp_available_congestion_control",
+            TcpAvailableCongestionControlFileOps::new_inode,
+        ),
+        (
+            "tcp_congestion_control",
+            TcpCongestionControlFileOps::new_inode,
+        ),
+    ];
+}
+
+impl DirOps for Ipv4DirOps {
+    fn lookup_child(&self, dir: &ProcDir<Self>, name: &str) -> Result<Arc<dyn Inode>> {
+        let mut cached_children = dir.cached_children().write();
+
+        if let Some(child) =
+            lookup_child_from_table(name, &mut cached_children, Self::STATIC_ENTRIES, |f| {
+                (f)(dir.this_weak().clone())
+            })
+        {
+            return Ok(child);
+        }
+
+        return_errno_with_message!(Errno::ENOENT, "the file does not exist");
+    }
+
+    fn populate_children<'a>(
+        &self,
+        dir: &'a ProcDir<Self>,
+    ) -> RwMutexUpgradeableGuard<'a, SlotVec<(String, Arc<dyn Inode>)>> {
+        let mut cached_children = dir.cached_children().write();
+
+        populate_children_from_table(&mut cached_children, Self::STATIC_ENTRIES, |f| {
+            (f)(dir.this_weak().clone())
+        });
+
+        cached_children.downgrade()
+    }
+}
+
+/// Represents the inode at `/proc/sys/net/ipv4/tcp_available_congestion_control`.
+struct TcpAvailableCongestionControlFileOps;
+
+impl TcpAvailableCongestionControlFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference:
+        // <https://elixir.bootlin.com/linux/v6.16.5/source/net/ipv4/sysctl_net_ipv4.c#L578>
+        ProcFileBuilder::new(Self, mkmod!(a+r))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for TcpAvailableCongestionControlFileOps {
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        let names = CongestionControl::ALL.map(|congestion| congestion.name());
+        writeln!(printer, "{}", names.join(" "))?;
+
+        Ok(printer.bytes_written())
+    }
+}
+
+/// Represents the inode at `/proc/sys/net/ipv4/tcp_congestion_control`.
+struct TcpCongestionControlFileOps;
+
+impl TcpCongestionControlFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference:
+        // <https://elixir.bootlin.com/linux/v6.16.5/source/net/ipv4/sysctl_net_ipv4.c#L1192>
+        ProcFileBuilder::new(Self, mkmod!(a+r, u+w))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for TcpCongestionControlFileOps {
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        let congestion = CongestionControl::default_for_new_sockets();
+        writeln!(printer, "{}", congestion.name())?;
+
+        Ok(printer.bytes_written())
+    }
+
+    fn write_at(&self, _offset: usize, reader: &mut VmReader) -> Result<usize> {
+        /// The maximum length of a congestion control name, including the trailing newline.
+        ///
+        /// Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/include/net/tcp.h#L1198>
+        const TCP_CA_NAME_MAX: usize = 16;
+
+        let (cstr, read_bytes) = reader.read_cstring_until_end(TCP_CA_NAME_MAX)?;
+        let name = cstr
+            .to_str()
+            .map_err(|_| Error::with_message(Errno::ENOENT, "non-UTF8 congestion name"))?;
+        let congestion = CongestionControl::new(name.trim())?;
+
+        congestion.set_default_for_new_sockets();
+
+        Ok(read_bytes)
+    }
+}
+
+/// Represents the inode at `/proc/sys/net/ipv4/ping_group_range`.
+struct PingGroupRangeFileOps;
+
+impl PingGroupRangeFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference:
+        // <https://elixir.bootlin.com/linux/v6.16.5/source/net/ipv4/sysctl_net_ipv4.c#L1013>
+        ProcFileBuilder::new(Self, mkmod!(a+r, u+w))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for PingGroupRangeFileOps {
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        let range = PingGroupRange::get();
+        writeln!(
+            printer,
+            "{}\t{}",
+            u32::from(range.start),
+            u32::from(range.end)
+        )?;
+
+        Ok(printer.bytes_written())
+    }
+
+    fn write_at(&self, _offset: usize, reader: &mut VmReader) -> Result<usize> {
+        /// The maximum length of the written range.
+        const MAX_RANGE_LEN: usize = 64;
+
+        let (cstr, read_bytes) = reader.read_cstring_until_end(MAX_RANGE_LEN)?;
+        let text = cstr
+            .to_str()
+            .map_err(|_| Error::with_message(Errno::EINVAL, "non-UTF8 group range"))?;
+
+        // Like Linux, if only one group ID is written, the end of the range is left unchanged.
+        // Extra group IDs are ignored.
+        let old_range = PingGroupRange::get();
+        let mut gids = text.split_whitespace().map(|gid| {
+            gid.parse::<u32>()
+                .map_err(|_| Error::with_message(Errno::EINVAL, "invalid group ID"))
+        });
+        let (start, end) = match (gids.next(), gids.next()) {
+            (Some(start), None) => (start?, u32::from(old_range.end)),
+            (Some(start), Some(end)) => (start?, end?),
+            (None, _) => return_errno_with_message!(Errno::EINVAL, "no group IDs are written"),
+        };
+
+        PingGroupRange::new(start, end)?.set();
+
+        Ok(read_bytes)
+    }
+}
@@ -0,0 +1,43 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use ostd::mm::frame::compaction::compact_memory;
+
+use crate::{
+    fs::{
+        file::mkmod,
+        procfs::template::{FileOps, ProcFileBuilder, read_i32_from},
+        vfs::inode::Inode,
+    },
+    prelude::*,
+};
+
+/// Represents the inode at `/proc/sys/vm/compact_memory`.
+pub struct CompactMemoryFileOps;
+
+impl CompactMemoryFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/mm/compaction.c>
+        ProcFileBuilder::new(Self, mkmod!(u+w))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for CompactMemoryFileOps {
+    fn read_at(&self, _offset: usize, _writer: &mut VmWriter) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "the compact_memory file cannot be read");
+    }
+
+    fn write_at(&self, _offset: usize, reader: &mut VmReader) -> Result<usize> {
+        let (val, read_bytes) = read_i32_from(reader)?;
+
+        // Like Linux, the memory is compacted only if 1 is written.
+        if val != 1 {
+            return_errno_with_message!(Errno::EINVAL, "only 1 can be written to compact memory");
+        }
+        compact_memory();
+
+        Ok(read_bytes)
+    }
+}
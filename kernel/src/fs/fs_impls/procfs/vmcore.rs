@@ -0,0 +1,32 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    fs::{
+        file::mkmod,
+        procfs::template::{FileOps, ProcFileBuilder},
+        vfs::inode::Inode,
+    },
+    power::vmcore::Vmcore,
+    prelude::*,
+};
+
+/// Represents the inode at `/proc/vmcore`.
+///
+/// The file exists only in the crash kernel.
+pub struct VmcoreFileOps(&'static Vmcore);
+
+impl VmcoreFileOps {
+    pub fn new_inode(vmcore: &'static Vmcore, parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        // Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/fs/proc/vmcore.c>
+        ProcFileBuilder::new(Self(vmcore), mkmod!(u+r))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for VmcoreFileOps {
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        self.0.read_at(offset, writer)
+    }
+}
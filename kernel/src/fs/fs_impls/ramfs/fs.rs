@@ -15,7 +15,7 @@ use ostd::{
     sync::{PreemptDisabled, RwLockWriteGuard},
 };
 
-use super::{memfd::MemfdInode, xattr::RamXattr, *};
+use super::{limits::RamFsLimits, memfd::MemfdInode, xattr::RamXattr, *};
 use crate::{
     device::{self, DeviceType},
     fs::{
@@ -47,12 +47,28 @@ pub struct RamFs {
     root: Arc<RamInode>,
     /// An inode allocator
     inode_allocator: AtomicU64,
+    /// The limits on the blocks and inodes
+    limits: RamFsLimits,
     /// FS event subscriber stats for this file system
     fs_event_subscriber_stats: FsEventSubscriberStats,
 }
 
 impl RamFs {
     pub fn new() -> Arc<Self> {
+        Self::new_with_limits(None, None)
+    }
+
+    /// Creates a `RamFs` that can use at most `max_blocks` blocks and `max_inodes` inodes.
+    ///
+    /// A limit of `None` means that the resource is unlimited.
+    pub(in crate::fs) fn new_with_limits(
+        max_blocks: Option<usize>,
+        max_inodes: Option<usize>,
+    ) -> Arc<Self> {
+        let limits = RamFsLimits::new(max_blocks, max_inodes);
+        // The root inode is always allocated.
+        limits.alloc_inode().unwrap();
+
         let anon_device_id = AnonDeviceId::acquire().expect("no device ID is available for ramfs");
         let root_dev_id = anon_device_id.id();
         Arc::new_cyclic(move |weak_fs| Self {
@@ -74,6 +90,7 @@ impl RamFs {
                 xattr: RamXattr::new(),
             }),
             inode_allocator: AtomicU64::new(ROOT_INO + 1),
+            limits,
             fs_event_subscriber_stats: FsEventSubscriberStats::new(),
         })
     }
@@ -81,6 +98,11 @@ impl RamFs {
     fn alloc_id(&self) -> u64 {
         self.inode_allocator.fetch_add(1, Ordering::Relaxed)
     }
+
+    /// Returns the limits on the blocks and inodes.
+    pub(in crate::fs) fn limits(&self) -> &RamFsLimits {
+        &self.limits
+    }
 }
 
 impl FileSystem for RamFs {
@@ -285,11 +307,6 @@ impl InodeMeta {
         }
     }
 
-    pub fn resize(&mut self, new_size: usize) {
-        self.size = new_size;
-        self.blocks = new_size.align_up(BLOCK_SIZE) / BLOCK_SIZE;
-    }
-
     pub fn inc_size(&mut self) {
         self.size += 1;
         self.blocks = self.size.align_up(BLOCK_SIZE) / BLOCK_SIZE;
@@ -574,6 +591,50 @@ impl RamInode {
         })
     }
 
+    /// Reserves the blocks of the regular file so that it has at least `nr_blocks` blocks.
+    ///
+    /// The blocks are charged to the file system, if any. It returns the number of the newly
+    /// reserved blocks, which should be given back by [`Self::unreserve_file_blocks`] if the
+    /// operation that needs them fails.
+    fn reserve_file_blocks(&self, nr_blocks: usize) -> Result<usize> {
+        let mut inode_meta = self.metadata.lock();
+        if nr_blocks <= inode_meta.blocks {
+            return Ok(0);
+        }
+
+        let nr_reserved = nr_blocks - inode_meta.blocks;
+        if let Some(fs) = self.fs.upgrade() {
+            fs.limits.alloc_blocks(nr_reserved)?;
+        }
+        inode_meta.blocks = nr_blocks;
+        Ok(nr_reserved)
+    }
+
+    /// Gives back the blocks reserved by [`Self::reserve_file_blocks`].
+    ///
+    /// The blocks that are used by the current file size are kept.
+    fn unreserve_file_blocks(&self, nr_reserved: usize) {
+        let mut inode_meta = self.metadata.lock();
+        let size_blocks = inode_meta.size.align_up(BLOCK_SIZE) / BLOCK_SIZE;
+        let nr_blocks = inode_meta
+            .blocks
+            .saturating_sub(nr_reserved)
+            .max(size_blocks);
+        self.release_file_blocks(&mut inode_meta, nr_blocks);
+    }
+
+    /// Releases the blocks of the regular file so that it has at most `nr_blocks` blocks.
+    fn release_file_blocks(&self, inode_meta: &mut InodeMeta, nr_blocks: usize) {
+        if nr_blocks >= inode_meta.blocks {
+            return;
+        }
+
+        if let Some(fs) = self.fs.upgrade() {
+            fs.limits.free_blocks(inode_meta.blocks - nr_blocks);
+        }
+        inode_meta.blocks = nr_blocks;
+    }
+
     fn find(&self, name: &str) -> Result<Arc<Self>> {
         if self.typ != InodeType::Dir {
             return_errno_with_message!(Errno::ENOTDIR, "self is not dir");
@@ -590,6 +651,19 @@ impl RamInode {
     }
 }
 
+impl Drop for RamInode {
+    fn drop(&mut self) {
+        let Some(fs) = self.fs.upgrade() else {
+            return;
+        };
+
+        if self.typ == InodeType::File {
+            fs.limits.free_blocks(self.metadata.get_mut().blocks);
+        }
+        fs.limits.free_inode();
+    }
+}
+
 impl PageCacheBackend for RamInode {
     fn read_page_async(&self, _idx: usize, frame: &CachePage) -> Result<BioWaiter> {
         // Initially, any block/page in a RamFs inode contains all zeros
@@ -646,11 +720,18 @@ impl InodeIo for RamInode {
 
                 let file_size = self.size();
                 let write_len = reader.remain();
-                let new_size = offset + write_len;
+                let new_size = offset
+                    .checked_add(write_len)
+                    .filter(|new_size| *new_size <= MAX_FILE_SIZE)
+                    .ok_or_else(|| Error::with_message(Errno::EFBIG, "the file is too large"))?;
                 let should_expand_size = new_size > file_size;
                 let new_size_aligned = new_size.align_up(BLOCK_SIZE);
                 if should_expand_size {
-                    page_cache.resize(new_size_aligned)?;
+                    let nr_reserved = self.reserve_file_blocks(new_size_aligned / BLOCK_SIZE)?;
+                    if let Err(err) = page_cache.resize(new_size_aligned) {
+                        self.unreserve_file_blocks(nr_reserved);
+                        return Err(err);
+                    }
                 }
                 page_cache.pages().write(offset, reader)?;
 
@@ -660,7 +741,6 @@ impl InodeIo for RamInode {
                 inode_meta.set_ctime(now);
                 if should_expand_size {
                     inode_meta.size = new_size;
-                    inode_meta.blocks = new_size_aligned / BLOCK_SIZE;
                 }
                 write_len
             }
@@ -689,19 +769,30 @@ impl Inode for RamInode {
             return_errno_with_message!(Errno::EINVAL, "the inode is not a regular file");
         }
 
+        if new_size > MAX_FILE_SIZE {
+            return_errno_with_message!(Errno::EFBIG, "the file is too large");
+        }
+
         let file_size = self.size();
         if file_size == new_size {
             return Ok(());
         }
 
+        let new_blocks = new_size.align_up(BLOCK_SIZE) / BLOCK_SIZE;
+        let nr_reserved = self.reserve_file_blocks(new_blocks)?;
+
         let page_cache = self.inner.as_file().unwrap();
-        page_cache.resize(new_size)?;
+        if let Err(err) = page_cache.resize(new_size) {
+            self.unreserve_file_blocks(nr_reserved);
+            return Err(err);
+        }
 
         let now = now();
         let mut inode_meta = self.metadata.lock();
         inode_meta.set_mtime(now);
         inode_meta.set_ctime(now);
-        inode_meta.resize(new_size);
+        inode_meta.size = new_size;
+        self.release_file_blocks(&mut inode_meta, new_blocks);
         Ok(())
     }
 
@@ -783,11 +874,13 @@ impl Inode for RamInode {
             return_errno_with_message!(Errno::EEXIST, "entry exists");
         }
 
+        let fs = self.fs.upgrade().unwrap();
+        fs.limits.alloc_inode()?;
         let new_inode = match type_ {
             MknodType::CharDevice(dev_id) | MknodType::BlockDevice(dev_id) => {
                 let dev_type = type_.device_type().unwrap();
                 RamInode::new_device(
-                    &self.fs.upgrade().unwrap(),
+                    &fs,
                     mode,
                     Uid::new_root(),
                     Gid::new_root(),
@@ -795,12 +888,9 @@ impl Inode for RamInode {
                     dev_id,
                 )
             }
-            MknodType::NamedPipe => RamInode::new_named_pipe(
-                &self.fs.upgrade().unwrap(),
-                mode,
-                Uid::new_root(),
-                Gid::new_root(),
-            ),
+            MknodType::NamedPipe => {
+                RamInode::new_named_pipe(&fs, mode, Uid::new_root(), Gid::new_root())
+            }
         };
 
         let mut self_dir = self_dir.upgrade();
@@ -833,6 +923,7 @@ impl Inode for RamInode {
         }
 
         let fs = self.fs.upgrade().unwrap();
+        fs.limits.alloc_inode()?;
         let new_inode = match type_ {
             InodeType::File => RamInode::new_file(&fs, mode, Uid::new_root(), Gid::new_root()),
             InodeType::SymLink => {
@@ -1326,6 +1417,7 @@ impl FsType for RamFsType {
     fn create(
         &self,
         _flags: FsFlags,
+        _source: Option<&str>,
         _args: Option<CString>,
         _disk: Option<Arc<dyn aster_block::BlockDevice>>,
     ) -> Result<Arc<dyn FileSystem>> {
@@ -0,0 +1,126 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::prelude::*;
+
+/// The limits on the space and the inodes that a `RamFs` can use.
+///
+/// A limit of `None` means that the resource is unlimited.
+pub struct RamFsLimits {
+    max_blocks: SpinLock<Option<usize>>,
+    used_blocks: AtomicUsize,
+    max_inodes: SpinLock<Option<usize>>,
+    used_inodes: AtomicUsize,
+}
+
+impl RamFsLimits {
+    /// Creates new limits with the maximum number of blocks and inodes.
+    pub(super) fn new(max_blocks: Option<usize>, max_inodes: Option<usize>) -> Self {
+        Self {
+            max_blocks: SpinLock::new(max_blocks),
+            used_blocks: AtomicUsize::new(0),
+            max_inodes: SpinLock::new(max_inodes),
+            used_inodes: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the maximum number of blocks.
+    pub fn max_blocks(&self) -> Option<usize> {
+        *self.max_blocks.lock()
+    }
+
+    /// Returns the number of the used blocks.
+    pub fn used_blocks(&self) -> usize {
+        self.used_blocks.load(Ordering::Relaxed)
+    }
+
+    /// Returns the maximum number of inodes.
+    pub fn max_inodes(&self) -> Option<usize> {
+        *self.max_inodes.lock()
+    }
+
+    /// Returns the number of the used inodes.
+    pub fn used_inodes(&self) -> usize {
+        self.used_inodes.load(Ordering::Relaxed)
+    }
+
+    /// Changes the maximum number of blocks and inodes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EINVAL` if a new limit is lower than the current usage, or if an
+    /// unlimited resource is going to be limited.
+    pub fn set_limits(&self, max_blocks: Option<usize>, max_inodes: Option<usize>) -> Result<()> {
+        // Lock both limits to change them atomically.
+        let mut old_max_blocks = self.max_blocks.lock();
+        let mut old_max_inodes = self.max_inodes.lock();
+
+        check_new_limit(*old_max_blocks, max_blocks, self.used_blocks())?;
+        check_new_limit(*old_max_inodes, max_inodes, self.used_inodes())?;
+
+        *old_max_blocks = max_blocks;
+        *old_max_inodes = max_inodes;
+        Ok(())
+    }
+
+    /// Allocates `nr_blocks` blocks.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ENOSPC` if there are not enough free blocks.
+    pub(super) fn alloc_blocks(&self, nr_blocks: usize) -> Result<()> {
+        let max_blocks = self.max_blocks.lock();
+        alloc_from(&self.used_blocks, nr_blocks, *max_blocks)
+    }
+
+    /// Frees `nr_blocks` blocks.
+    pub(super) fn free_blocks(&self, nr_blocks: usize) {
+        let old_used_blocks = self.used_blocks.fetch_sub(nr_blocks, Ordering::Relaxed);
+        debug_assert!(old_used_blocks >= nr_blocks);
+    }
+
+    /// Allocates an inode.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ENOSPC` if there are no free inodes.
+    pub(super) fn alloc_inode(&self) -> Result<()> {
+        let max_inodes = self.max_inodes.lock();
+        alloc_from(&self.used_inodes, 1, *max_inodes)
+    }
+
+    /// Frees an inode.
+    pub(super) fn free_inode(&self) {
+        let old_used_inodes = self.used_inodes.fetch_sub(1, Ordering::Relaxed);
+        debug_assert!(old_used_inodes >= 1);
+    }
+}
+
+/// Checks whether a limit can be changed from `old_max` to `new_max`.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.16/source/mm/shmem.c#L4865>
+fn check_new_limit(old_max: Option<usize>, new_max: Option<usize>, used: usize) -> Result<()> {
+    match (old_max, new_max) {
+        (None, Some(_)) => {
+            return_errno_with_message!(Errno::EINVAL, "an unlimited resource cannot be limited")
+        }
+        (_, Some(new_max)) if new_max < used => {
+            return_errno_with_message!(Errno::EINVAL, "the limit is lower than the usage")
+        }
+        _ => Ok(()),
+    }
+}
+
+fn alloc_from(used: &AtomicUsize, nr: usize, max: Option<usize>) -> Result<()> {
+    let Some(max) = max else {
+        used.fetch_add(nr, Ordering::Relaxed);
+        return Ok(());
+    };
+
+    used.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+        used.checked_add(nr).filter(|new_used| *new_used <= max)
+    })
+    .map_err(|_| Error::with_message(Errno::ENOSPC, "no space left in the file system"))?;
+    Ok(())
+}
@@ -6,6 +6,7 @@ pub use fs::RamFs;
 use fs::RamFsType;
 
 mod fs;
+mod limits;
 pub mod memfd;
 mod xattr;
 
@@ -13,6 +14,8 @@ const RAMFS_MAGIC: u64 = 0x8584_58f6;
 const BLOCK_SIZE: usize = 4096;
 const ROOT_INO: u64 = 1;
 const NAME_MAX: usize = 255;
+/// The maximum size of a regular file, which is the same as Linux's `MAX_LFS_FILESIZE`.
+const MAX_FILE_SIZE: usize = i64::MAX as usize;
 
 pub(super) fn init() {
     crate::fs::vfs::registry::register(&RamFsType).unwrap();
@@ -0,0 +1,111 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::sync::Arc;
+
+use aster_block::BlockDevice;
+use aster_systree::SysNode;
+use spin::Once;
+
+use super::inode::SecurityInode;
+use crate::{
+    fs::{
+        Result,
+        securityfs::systree_node::SecurityRootNode,
+        pseudofs::AnonDeviceId,
+        utils::systree_inode::SysTreeInodeTy,
+        vfs::{
+            file_system::{FileSystem, FsEventSubscriberStats, FsFlags, SuperBlock},
+            inode::Inode,
+            registry::{FsProperties, FsType},
+        },
+    },
+    prelude::*,
+};
+
+/// A file system that exports the interfaces of security modules.
+///
+/// `SecurityFs` is a RAM-based file system where security modules can place files
+/// to expose their states and to receive their policies from user space.
+pub struct SecurityFs {
+    _anon_device_id: AnonDeviceId,
+    sb: SuperBlock,
+    root: Arc<dyn Inode>,
+    fs_event_subscriber_stats: FsEventSubscriberStats,
+}
+
+// Magic number for `SecurityFs` (taken from Linux).
+const MAGIC_NUMBER: u64 = 0x73636673;
+const BLOCK_SIZE: usize = 4096;
+const NAME_MAX: usize = 255;
+
+impl SecurityFs {
+    /// Returns the `SecurityFs` singleton.
+    pub(super) fn singleton() -> &'static Arc<SecurityFs> {
+        static SINGLETON: Once<Arc<SecurityFs>> = Once::new();
+
+        SINGLETON.call_once(|| Self::new(SecurityRootNode::singleton().clone()))
+    }
+
+    fn new(root_node: Arc<SecurityRootNode>) -> Arc<Self> {
+        let anon_device_id =
+            AnonDeviceId::acquire().expect("no device ID is available for securityfs");
+        let sb = SuperBlock::new(MAGIC_NUMBER, BLOCK_SIZE, NAME_MAX, anon_device_id.id());
+        let root_inode = SecurityInode::new_root(root_node, &sb);
+
+        Arc::new(Self {
+            _anon_device_id: anon_device_id,
+            sb,
+            root: root_inode,
+            fs_event_subscriber_stats: FsEventSubscriberStats::new(),
+        })
+    }
+}
+
+impl FileSystem for SecurityFs {
+    fn name(&self) -> &'static str {
+        "securityfs"
+    }
+
+    fn sync(&self) -> Result<()> {
+        // `SecurityFs` is volatile, sync is a no-op
+        Ok(())
+    }
+
+    fn root_inode(&self) -> Arc<dyn Inode> {
+        self.root.clone()
+    }
+
+    fn sb(&self) -> SuperBlock {
+        self.sb.clone()
+    }
+
+    fn fs_event_subscriber_stats(&self) -> &FsEventSubscriberStats {
+        &self.fs_event_subscriber_stats
+    }
+}
+
+pub(super) struct SecurityFsType;
+
+impl FsType for SecurityFsType {
+    fn name(&self) -> &'static str {
+        "securityfs"
+    }
+
+    fn properties(&self) -> FsProperties {
+        FsProperties::empty()
+    }
+
+    fn create(
+        &self,
+        _flags: FsFlags,
+        _source: Option<&str>,
+        _args: Option<CString>,
+        _disk: Option<Arc<dyn BlockDevice>>,
+    ) -> Result<Arc<dyn FileSystem>> {
+        Ok(SecurityFs::singleton().clone() as _)
+    }
+
+    fn sysnode(&self) -> Option<Arc<dyn SysNode>> {
+        None
+    }
+}
@@ -0,0 +1,40 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Security file system (securityfs).
+//!
+//! Securityfs is where security modules export their interfaces to user space, such as the
+//! interface to load the policy. A security module registers a [`SecurityDir`] under the root
+//! of securityfs, whose files are [`SecurityFile`]s backed by closures.
+//!
+//! Securityfs is usually mounted at `/sys/kernel/security`.
+
+use alloc::sync::Arc;
+
+use aster_systree::{EmptyNode, SysBranchNode};
+use systree_node::SecurityRootNode;
+pub use systree_node::{SecurityDir, SecurityFile};
+
+use crate::{fs::securityfs::fs::SecurityFsType, prelude::*};
+
+mod fs;
+mod inode;
+mod systree_node;
+
+// This method should be called during kernel file system initialization,
+// _after_ `aster_systree::init`.
+pub(super) fn init() {
+    let security_kernel_sysnode = EmptyNode::new("security".into());
+    super::sysfs::register_kernel_sysnode(security_kernel_sysnode).unwrap();
+
+    crate::fs::vfs::registry::register(&SecurityFsType).unwrap();
+}
+
+/// Registers a top-level directory under the root of securityfs.
+///
+/// If a directory with the same name has already been registered,
+/// this function returns an error.
+pub fn register_dir(dir: Arc<SecurityDir>) -> Result<()> {
+    SecurityRootNode::singleton().add_child(dir)?;
+
+    Ok(())
+}
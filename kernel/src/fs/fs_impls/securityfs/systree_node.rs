@@ -0,0 +1,176 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{
+    boxed::Box,
+    string::String,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::fmt::Debug;
+
+use aster_systree::{
+    BranchNodeFields, Error, MAX_ATTR_SIZE, Result, SysAttrSet, SysAttrSetBuilder, SysBranchNode,
+    SysObj, SysPerms, SysStr, inherit_sys_branch_node,
+};
+use aster_util::printer::VmPrinter;
+use inherit_methods_macro::inherit_methods;
+use ostd::mm::{VmReader, VmWriter};
+use spin::Once;
+
+use crate::prelude::Errno;
+
+/// The `SysTree` node that represents the root node of the `SecurityFs`.
+#[derive(Debug)]
+pub struct SecurityRootNode {
+    fields: BranchNodeFields<dyn SysObj, Self>,
+}
+
+#[inherit_methods(from = "self.fields")]
+impl SecurityRootNode {
+    /// Returns the `SecurityRootNode` singleton.
+    pub(super) fn singleton() -> &'static Arc<SecurityRootNode> {
+        static SINGLETON: Once<Arc<SecurityRootNode>> = Once::new();
+
+        SINGLETON.call_once(Self::new)
+    }
+
+    fn new() -> Arc<Self> {
+        let name = SysStr::from("security");
+
+        let attrs = SysAttrSet::new_empty();
+        Arc::new_cyclic(|weak_self| {
+            let fields = BranchNodeFields::new(name, attrs, weak_self.clone());
+            SecurityRootNode { fields }
+        })
+    }
+
+    /// Adds a child node.
+    pub fn add_child(&self, new_child: Arc<dyn SysObj>) -> Result<()>;
+}
+
+inherit_sys_branch_node!(SecurityRootNode, fields, {
+    fn is_root(&self) -> bool {
+        true
+    }
+
+    fn init_parent(&self, _parent: Weak<dyn SysBranchNode>) {
+        // This method should be a no-op for `RootNode`.
+    }
+
+    fn perms(&self) -> SysPerms {
+        SysPerms::DEFAULT_RW_PERMS
+    }
+});
+
+/// A directory in securityfs.
+///
+/// The files in the directory are specified when the directory is created.
+/// Subdirectories can be added at any time.
+pub struct SecurityDir {
+    fields: BranchNodeFields<dyn SysObj, Self>,
+    files: Vec<SecurityFile>,
+}
+
+#[inherit_methods(from = "self.fields")]
+impl SecurityDir {
+    /// Creates a directory with the files.
+    pub fn new(name: impl Into<SysStr>, files: Vec<SecurityFile>) -> Arc<Self> {
+        let mut builder = SysAttrSetBuilder::new();
+        for file in files.iter() {
+            let perms = if file.store.is_some() {
+                SysPerms::DEFAULT_RW_ATTR_PERMS
+            } else {
+                SysPerms::DEFAULT_RO_ATTR_PERMS
+            };
+            builder.add(SysStr::from(file.name), perms);
+        }
+        let attrs = builder
+            .build()
+            .expect("Failed to build the attribute set of a securityfs directory");
+
+        Arc::new_cyclic(|weak_self| {
+            let fields = BranchNodeFields::new(name.into(), attrs, weak_self.clone());
+            SecurityDir { fields, files }
+        })
+    }
+
+    /// Adds a subdirectory.
+    pub fn add_child(&self, new_child: Arc<dyn SysObj>) -> Result<()>;
+
+    fn file(&self, name: &str) -> Option<&SecurityFile> {
+        self.files.iter().find(|file| file.name == name)
+    }
+}
+
+impl Debug for SecurityDir {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SecurityDir")
+            .field("name", self.fields.name())
+            .finish_non_exhaustive()
+    }
+}
+
+inherit_sys_branch_node!(SecurityDir, fields, {
+    fn read_attr_at(&self, name: &str, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let file = self.file(name).ok_or(Error::AttributeError)?;
+
+        let mut printer = VmPrinter::new_skip(writer, offset);
+        write!(printer, "{}", (file.show)())?;
+
+        Ok(printer.bytes_written())
+    }
+
+    fn write_attr(&self, name: &str, reader: &mut VmReader) -> Result<usize> {
+        let file = self.file(name).ok_or(Error::AttributeError)?;
+        let Some(store) = file.store.as_ref() else {
+            return Err(Error::PermissionDenied);
+        };
+
+        let (content, len) = reader
+            .read_cstring_until_end(MAX_ATTR_SIZE)
+            .map_err(|_| Error::PageFault)?;
+        let content = content.to_str().map_err(|_| Error::InvalidOperation)?;
+        store(content).map_err(|err| match err.error() {
+            Errno::EPERM | Errno::EACCES => Error::PermissionDenied,
+            Errno::ENOENT => Error::NotFound,
+            Errno::EEXIST => Error::AlreadyExists,
+            _ => Error::InvalidOperation,
+        })?;
+
+        Ok(len)
+    }
+
+    fn perms(&self) -> SysPerms {
+        SysPerms::DEFAULT_RW_PERMS
+    }
+});
+
+/// A file in securityfs, whose content is generated and consumed by closures.
+pub struct SecurityFile {
+    name: &'static str,
+    show: Box<dyn Fn() -> String + Send + Sync>,
+    store: Option<Box<dyn Fn(&str) -> crate::prelude::Result<()> + Send + Sync>>,
+}
+
+impl SecurityFile {
+    /// Creates a read-only file whose content is generated by `show`.
+    pub fn new<F>(name: &'static str, show: F) -> Self
+    where
+        F: Fn() -> String + Send + Sync + 'static,
+    {
+        Self {
+            name,
+            show: Box::new(show),
+            store: None,
+        }
+    }
+
+    /// Makes the file writable, where the written content is consumed by `store`.
+    pub fn with_store<F>(mut self, store: F) -> Self
+    where
+        F: Fn(&str) -> crate::prelude::Result<()> + Send + Sync + 'static,
+    {
+        self.store = Some(Box::new(store));
+        self
+    }
+}
@@ -0,0 +1,48 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core2::io::Read;
+use libflate::zlib::Decoder as ZlibDecoder;
+
+use super::zstd;
+use crate::prelude::*;
+
+/// The compression algorithm of a squashfs image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Compressor {
+    Zlib,
+    Zstd,
+}
+
+impl Compressor {
+    pub(super) fn from_id(id: u16) -> Result<Self> {
+        match id {
+            1 => Ok(Self::Zlib),
+            6 => Ok(Self::Zstd),
+            2..=5 => {
+                return_errno_with_message!(Errno::EINVAL, "unsupported squashfs compressor")
+            }
+            _ => return_errno_with_message!(Errno::EINVAL, "invalid squashfs compressor"),
+        }
+    }
+
+    /// Decompresses a block that is at most `max_len` bytes after decompression.
+    pub(super) fn decompress(&self, src: &[u8], max_len: usize) -> Result<Vec<u8>> {
+        match self {
+            Self::Zlib => {
+                let mut decoder = ZlibDecoder::new(src)
+                    .map_err(|_| Error::with_message(Errno::EIO, "invalid zlib header"))?;
+                let mut output = Vec::with_capacity(max_len);
+                // One more byte is read to tell whether the output is too large.
+                (&mut decoder)
+                    .take(max_len as u64 + 1)
+                    .read_to_end(&mut output)
+                    .map_err(|_| Error::with_message(Errno::EIO, "corrupted zlib data"))?;
+                if output.len() > max_len {
+                    return_errno_with_message!(Errno::EIO, "the zlib output is too large");
+                }
+                Ok(output)
+            }
+            Self::Zstd => zstd::decompress(src, max_len),
+        }
+    }
+}
@@ -0,0 +1,362 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::num::NonZeroUsize;
+
+use aster_block::{BlockDevice, SECTOR_SIZE};
+use lru::LruCache;
+use ostd::mm::VmIo;
+
+use super::{
+    inode::{RawInodeHeader, SquashInode},
+    super_block::{METADATA_SIZE, SQUASHFS_MAGIC, SquashSuperBlock},
+};
+use crate::{
+    fs::{
+        file::InodeType,
+        vfs::{
+            file_system::{FileSystem, FsEventSubscriberStats, FsFlags, SuperBlock},
+            inode::Inode,
+            registry::{FsProperties, FsType},
+        },
+    },
+    prelude::*,
+};
+
+/// The maximum length of the names in squashfs.
+pub(super) const MAX_NAME_LEN: usize = 256;
+
+/// The number of the decompressed metadata blocks that are cached.
+const METADATA_CACHE_SIZE: usize = 64;
+/// The number of the decompressed data blocks and fragment blocks that are cached.
+const DATA_CACHE_SIZE: usize = 16;
+
+/// The size of a fragment entry.
+const FRAGMENT_ENTRY_SIZE: usize = 16;
+
+/// A read-only squashfs filesystem.
+pub struct SquashFs {
+    block_device: Arc<dyn BlockDevice>,
+    super_block: SquashSuperBlock,
+    /// The user and group IDs, which are referred to by their indexes in the inodes.
+    ids: Vec<u32>,
+    /// The locations of the metadata blocks of the fragment table.
+    fragment_table_blocks: Vec<u64>,
+    metadata_cache: Mutex<LruCache<u64, Arc<MetadataBlock>>>,
+    data_cache: Mutex<LruCache<u64, Arc<Vec<u8>>>>,
+    /// The loaded inodes, indexed by their inode numbers.
+    inodes: Mutex<BTreeMap<u32, Arc<SquashInode>>>,
+    root_ino: u32,
+    fs_event_subscriber_stats: FsEventSubscriberStats,
+}
+
+/// A decompressed metadata block.
+pub(super) struct MetadataBlock {
+    data: Vec<u8>,
+    /// The location of the next metadata block.
+    next: u64,
+}
+
+/// A position in the metadata, i.e., the location of a metadata block and the offset
+/// within the decompressed block.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct MetadataPos {
+    pub(super) block: u64,
+    pub(super) offset: usize,
+}
+
+/// The location of a fragment block.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C, packed)]
+struct RawFragmentEntry {
+    start: u64,
+    size: u32,
+    unused: u32,
+}
+
+impl SquashFs {
+    /// Opens a squashfs filesystem on the block device.
+    pub(super) fn open(block_device: Arc<dyn BlockDevice>) -> Result<Arc<Self>> {
+        let super_block = SquashSuperBlock::read(block_device.as_ref())?;
+        let mut fs = Self {
+            block_device,
+            super_block,
+            ids: Vec::new(),
+            fragment_table_blocks: Vec::new(),
+            metadata_cache: Mutex::new(LruCache::new(
+                NonZeroUsize::new(METADATA_CACHE_SIZE).unwrap(),
+            )),
+            data_cache: Mutex::new(LruCache::new(NonZeroUsize::new(DATA_CACHE_SIZE).unwrap())),
+            inodes: Mutex::new(BTreeMap::new()),
+            root_ino: 0,
+            fs_event_subscriber_stats: FsEventSubscriberStats::new(),
+        };
+
+        fs.ids = fs.read_lookup_table(super_block.id_table, super_block.id_count as usize)?;
+        if super_block.fragment_count > 0 {
+            let nr_blocks =
+                (super_block.fragment_count as usize * FRAGMENT_ENTRY_SIZE).div_ceil(METADATA_SIZE);
+            let mut locations = vec![0u64; nr_blocks];
+            fs.read_bytes(super_block.fragment_table, locations.as_mut_bytes())?;
+            fs.fragment_table_blocks = locations;
+        }
+
+        let mut root_pos = fs.inode_pos(super_block.root_inode);
+        fs.root_ino = fs.read_metadata_val::<RawInodeHeader>(&mut root_pos)?.ino;
+
+        let fs = Arc::new(fs);
+        let root = SquashInode::load(&fs, super_block.root_inode)?;
+        if root.type_() != InodeType::Dir {
+            return_errno_with_message!(Errno::EINVAL, "the root inode is not a directory");
+        }
+
+        Ok(fs)
+    }
+
+    pub(super) fn super_block(&self) -> &SquashSuperBlock {
+        &self.super_block
+    }
+
+    pub(super) fn block_device(&self) -> &dyn BlockDevice {
+        self.block_device.as_ref()
+    }
+
+    /// Returns the position of an inode from its reference.
+    ///
+    /// The reference consists of the location of the metadata block relative to the inode
+    /// table (bits 16-47) and the offset within the block (bits 0-15).
+    pub(super) fn inode_pos(&self, inode_ref: u64) -> MetadataPos {
+        MetadataPos {
+            block: self.super_block.inode_table + (inode_ref >> 16),
+            offset: (inode_ref & 0xFFFF) as usize,
+        }
+    }
+
+    /// Returns the user or group ID of an index.
+    pub(super) fn id(&self, idx: u16) -> Result<u32> {
+        self.ids
+            .get(idx as usize)
+            .copied()
+            .ok_or_else(|| Error::with_message(Errno::EIO, "invalid squashfs ID index"))
+    }
+
+    /// Returns the loaded inode with the inode number.
+    pub(super) fn cached_inode(&self, ino: u32) -> Option<Arc<SquashInode>> {
+        self.inodes.lock().get(&ino).cloned()
+    }
+
+    pub(super) fn cache_inode(&self, inode: Arc<SquashInode>) -> Arc<SquashInode> {
+        self.inodes
+            .lock()
+            .entry(inode.squash_ino())
+            .or_insert(inode)
+            .clone()
+    }
+
+    /// Reads the bytes at any offset of the device.
+    pub(super) fn read_bytes(&self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        let end = offset
+            .checked_add(buf.len() as u64)
+            .filter(|end| *end <= self.super_block.bytes_used)
+            .ok_or_else(|| Error::with_message(Errno::EIO, "read beyond the squashfs image"))?;
+        if buf.is_empty() {
+            return Ok(());
+        }
+
+        // The device can only be read in sectors.
+        let sector_size = SECTOR_SIZE as u64;
+        let aligned_start = offset / sector_size * sector_size;
+        let aligned_end = end.div_ceil(sector_size) * sector_size;
+        let mut sectors = vec![0u8; (aligned_end - aligned_start) as usize];
+        self.block_device
+            .read_bytes(aligned_start as usize, &mut sectors)?;
+
+        let start = (offset - aligned_start) as usize;
+        buf.copy_from_slice(&sectors[start..start + buf.len()]);
+        Ok(())
+    }
+
+    /// Reads `buf.len()` bytes of metadata starting from `pos`, and advances `pos`.
+    pub(super) fn read_metadata(&self, pos: &mut MetadataPos, buf: &mut [u8]) -> Result<()> {
+        let mut nr_read = 0;
+        while nr_read < buf.len() {
+            let block = self.metadata_block(pos.block)?;
+            if pos.offset >= block.data.len() {
+                if pos.offset > block.data.len() {
+                    return_errno_with_message!(Errno::EIO, "invalid squashfs metadata offset");
+                }
+                *pos = MetadataPos {
+                    block: block.next,
+                    offset: 0,
+                };
+                continue;
+            }
+
+            let len = (block.data.len() - pos.offset).min(buf.len() - nr_read);
+            buf[nr_read..nr_read + len].copy_from_slice(&block.data[pos.offset..pos.offset + len]);
+            nr_read += len;
+            pos.offset += len;
+        }
+        Ok(())
+    }
+
+    /// Reads a value of metadata starting from `pos`, and advances `pos`.
+    pub(super) fn read_metadata_val<T: Pod>(&self, pos: &mut MetadataPos) -> Result<T> {
+        let mut val = T::new_zeroed();
+        self.read_metadata(pos, val.as_mut_bytes())?;
+        Ok(val)
+    }
+
+    fn metadata_block(&self, location: u64) -> Result<Arc<MetadataBlock>> {
+        if let Some(block) = self.metadata_cache.lock().get(&location) {
+            return Ok(block.clone());
+        }
+
+        let mut header = [0u8; 2];
+        self.read_bytes(location, &mut header)?;
+        let header = u16::from_le_bytes(header);
+        let size = (header & 0x7FFF) as usize;
+        let is_compressed = header & 0x8000 == 0;
+        if size == 0 || size > METADATA_SIZE {
+            return_errno_with_message!(Errno::EIO, "invalid squashfs metadata block");
+        }
+
+        let mut raw = vec![0u8; size];
+        self.read_bytes(location + 2, &mut raw)?;
+        let data = if is_compressed {
+            self.super_block
+                .compressor
+                .decompress(&raw, METADATA_SIZE)?
+        } else {
+            raw
+        };
+
+        let block = Arc::new(MetadataBlock {
+            data,
+            next: location + 2 + size as u64,
+        });
+        self.metadata_cache.lock().put(location, block.clone());
+        Ok(block)
+    }
+
+    /// Reads a data block or a fragment block.
+    ///
+    /// The `size` is the on-disk size, whose bit 24 is set if the block is stored
+    /// uncompressed.
+    pub(super) fn read_data_block(&self, location: u64, size: u32) -> Result<Arc<Vec<u8>>> {
+        if let Some(block) = self.data_cache.lock().get(&location) {
+            return Ok(block.clone());
+        }
+
+        let is_compressed = size & (1 << 24) == 0;
+        let size = (size & 0xFF_FFFF) as usize;
+        let block_size = self.super_block.block_size;
+        if size > block_size {
+            return_errno_with_message!(Errno::EIO, "invalid squashfs data block size");
+        }
+
+        let mut raw = vec![0u8; size];
+        self.read_bytes(location, &mut raw)?;
+        let data = if is_compressed {
+            self.super_block.compressor.decompress(&raw, block_size)?
+        } else {
+            raw
+        };
+
+        let block = Arc::new(data);
+        self.data_cache.lock().put(location, block.clone());
+        Ok(block)
+    }
+
+    /// Returns the location and the on-disk size of a fragment block.
+    pub(super) fn fragment(&self, idx: u32) -> Result<(u64, u32)> {
+        if idx >= self.super_block.fragment_count {
+            return_errno_with_message!(Errno::EIO, "invalid squashfs fragment index");
+        }
+
+        let offset = idx as usize * FRAGMENT_ENTRY_SIZE;
+        let mut pos = MetadataPos {
+            block: self.fragment_table_blocks[offset / METADATA_SIZE],
+            offset: offset % METADATA_SIZE,
+        };
+        let entry = self.read_metadata_val::<RawFragmentEntry>(&mut pos)?;
+        Ok((entry.start, entry.size))
+    }
+
+    /// Reads a table of 32-bit entries, whose metadata blocks are given by a lookup table.
+    fn read_lookup_table(&self, location: u64, count: usize) -> Result<Vec<u32>> {
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+
+        // The metadata blocks of a table are stored consecutively.
+        let mut first_block = 0u64;
+        self.read_bytes(location, first_block.as_mut_bytes())?;
+        let mut entries = vec![0u32; count];
+        let mut pos = MetadataPos {
+            block: first_block,
+            offset: 0,
+        };
+        self.read_metadata(&mut pos, entries.as_mut_bytes())?;
+        Ok(entries)
+    }
+}
+
+impl FileSystem for SquashFs {
+    fn name(&self) -> &'static str {
+        "squashfs"
+    }
+
+    fn sync(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn root_inode(&self) -> Arc<dyn Inode> {
+        self.cached_inode(self.root_ino).unwrap()
+    }
+
+    fn sb(&self) -> SuperBlock {
+        let block_size = self.super_block.block_size;
+        let mut sb = SuperBlock::new(
+            SQUASHFS_MAGIC as u64,
+            block_size,
+            MAX_NAME_LEN,
+            self.block_device.id(),
+        );
+        sb.blocks = (self.super_block.bytes_used as usize).div_ceil(block_size);
+        sb.bfree = 0;
+        sb.bavail = 0;
+        sb.files = self.super_block.inode_count as usize;
+        sb.ffree = 0;
+        sb
+    }
+
+    fn fs_event_subscriber_stats(&self) -> &FsEventSubscriberStats {
+        &self.fs_event_subscriber_stats
+    }
+}
+
+pub(super) struct SquashType;
+
+impl FsType for SquashType {
+    fn name(&self) -> &'static str {
+        "squashfs"
+    }
+
+    fn properties(&self) -> FsProperties {
+        FsProperties::NEED_DISK
+    }
+
+    fn create(
+        &self,
+        _flags: FsFlags,
+        _source: Option<&str>,
+        _args: Option<CString>,
+        disk: Option<Arc<dyn BlockDevice>>,
+    ) -> Result<Arc<dyn FileSystem>> {
+        SquashFs::open(disk.unwrap()).map(|fs| fs as _)
+    }
+
+    fn sysnode(&self) -> Option<Arc<dyn aster_systree::SysNode>> {
+        None
+    }
+}
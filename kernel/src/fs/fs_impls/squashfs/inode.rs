@@ -0,0 +1,644 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use aster_block::{SECTOR_SIZE, bio::BioWaiter};
+use device_id::DeviceId;
+use ostd::mm::{VmIo, io::util::HasVmReaderWriter};
+
+use super::fs::{MetadataPos, SquashFs};
+use crate::{
+    fs::{
+        file::{InodeMode, InodeType, StatusFlags},
+        utils::DirentVisitor,
+        vfs::{
+            file_system::FileSystem,
+            inode::{Extension, Inode, InodeIo, Metadata, MknodType, SymbolicLink},
+            page_cache::{CachePage, PageCache, PageCacheBackend},
+        },
+    },
+    prelude::*,
+    process::{Gid, Uid},
+    vm::vmo::Vmo,
+};
+
+/// The fragment index of the files whose tails are not stored in fragments.
+const NO_FRAGMENT: u32 = 0xFFFF_FFFF;
+
+/// The bit of the on-disk block sizes that indicates an uncompressed block.
+const UNCOMPRESSED_BLOCK: u32 = 1 << 24;
+
+/// The common header of the on-disk inodes.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct RawInodeHeader {
+    type_: u16,
+    mode: u16,
+    uid_idx: u16,
+    gid_idx: u16,
+    mtime: u32,
+    pub(super) ino: u32,
+}
+
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Pod)]
+struct RawDirInode {
+    block_index: u32,
+    nlink: u32,
+    size: u16,
+    block_offset: u16,
+    parent_ino: u32,
+}
+
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Pod)]
+struct RawExtDirInode {
+    nlink: u32,
+    size: u32,
+    block_index: u32,
+    parent_ino: u32,
+    index_count: u16,
+    block_offset: u16,
+    xattr_idx: u32,
+}
+
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Pod)]
+struct RawFileInode {
+    blocks_start: u32,
+    fragment_idx: u32,
+    fragment_offset: u32,
+    size: u32,
+}
+
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Pod)]
+struct RawExtFileInode {
+    blocks_start: u64,
+    size: u64,
+    sparse: u64,
+    nlink: u32,
+    fragment_idx: u32,
+    fragment_offset: u32,
+    xattr_idx: u32,
+}
+
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Pod)]
+struct RawSymlinkInode {
+    nlink: u32,
+    target_size: u32,
+}
+
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Pod)]
+struct RawDevInode {
+    nlink: u32,
+    device: u32,
+}
+
+/// The header of a run of directory entries, whose inodes are in the same metadata block.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Pod)]
+struct RawDirHeader {
+    /// The number of entries minus one.
+    count: u32,
+    /// The location of the metadata block of the inodes, relative to the inode table.
+    start: u32,
+    /// The base inode number of the entries.
+    ino: u32,
+}
+
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Pod)]
+struct RawDirEntry {
+    /// The offset of the inode within the metadata block.
+    offset: u16,
+    /// The difference between the inode number and the base one.
+    ino_delta: i16,
+    type_: u16,
+    /// The length of the name minus one.
+    name_size: u16,
+}
+
+/// An inode of squashfs.
+pub(super) struct SquashInode {
+    ino: u32,
+    type_: InodeType,
+    mode: u16,
+    uid: Uid,
+    gid: Gid,
+    mtime: Duration,
+    nlink: u32,
+    size: usize,
+    data: InodeData,
+    /// The page cache of a regular file.
+    page_cache: Option<PageCache>,
+    fs: Weak<SquashFs>,
+    extension: Extension,
+}
+
+enum InodeData {
+    Dir {
+        entries: Vec<DirEntry>,
+        parent_ino: u32,
+    },
+    File {
+        /// The locations and the on-disk sizes of the data blocks.
+        blocks: Vec<(u64, u32)>,
+        /// The fragment index and the offset within the fragment block of the tail.
+        fragment: Option<(u32, u32)>,
+    },
+    Symlink(String),
+    Device(u32),
+    Ipc,
+}
+
+struct DirEntry {
+    name: String,
+    inode_ref: u64,
+    ino: u32,
+    type_: InodeType,
+}
+
+impl SquashInode {
+    /// Loads the inode of a reference, or returns the loaded one.
+    pub(super) fn load(fs: &Arc<SquashFs>, inode_ref: u64) -> Result<Arc<Self>> {
+        let mut pos = fs.inode_pos(inode_ref);
+        let header = fs.read_metadata_val::<RawInodeHeader>(&mut pos)?;
+        if let Some(inode) = fs.cached_inode(header.ino) {
+            return Ok(inode);
+        }
+
+        let (type_, nlink, size, data) = match header.type_ {
+            1 => {
+                let raw = fs.read_metadata_val::<RawDirInode>(&mut pos)?;
+                let entries =
+                    read_dir_entries(fs, raw.block_index, raw.block_offset, raw.size as usize)?;
+                let data = InodeData::Dir {
+                    entries,
+                    parent_ino: raw.parent_ino,
+                };
+                (InodeType::Dir, raw.nlink, raw.size as usize, data)
+            }
+            8 => {
+                let raw = fs.read_metadata_val::<RawExtDirInode>(&mut pos)?;
+                let entries =
+                    read_dir_entries(fs, raw.block_index, raw.block_offset, raw.size as usize)?;
+                let data = InodeData::Dir {
+                    entries,
+                    parent_ino: raw.parent_ino,
+                };
+                (InodeType::Dir, raw.nlink, raw.size as usize, data)
+            }
+            2 => {
+                let raw = fs.read_metadata_val::<RawFileInode>(&mut pos)?;
+                let size = raw.size as usize;
+                let data = read_file_layout(
+                    fs,
+                    &mut pos,
+                    raw.blocks_start as u64,
+                    size,
+                    raw.fragment_idx,
+                    raw.fragment_offset,
+                )?;
+                (InodeType::File, 1, size, data)
+            }
+            9 => {
+                let raw = fs.read_metadata_val::<RawExtFileInode>(&mut pos)?;
+                let size = usize::try_from(raw.size)
+                    .map_err(|_| Error::with_message(Errno::EIO, "the file is too large"))?;
+                let data = read_file_layout(
+                    fs,
+                    &mut pos,
+                    raw.blocks_start,
+                    size,
+                    raw.fragment_idx,
+                    raw.fragment_offset,
+                )?;
+                (InodeType::File, raw.nlink, size, data)
+            }
+            3 | 10 => {
+                let raw = fs.read_metadata_val::<RawSymlinkInode>(&mut pos)?;
+                let size = raw.target_size as usize;
+                if size > PAGE_SIZE {
+                    return_errno_with_message!(Errno::EIO, "the symlink target is too long");
+                }
+                let mut target = vec![0u8; size];
+                fs.read_metadata(&mut pos, &mut target)?;
+                let target = String::from_utf8_lossy(&target).into_owned();
+                (
+                    InodeType::SymLink,
+                    raw.nlink,
+                    size,
+                    InodeData::Symlink(target),
+                )
+            }
+            4 | 11 | 5 | 12 => {
+                let raw = fs.read_metadata_val::<RawDevInode>(&mut pos)?;
+                let type_ = if header.type_ % 7 == 4 {
+                    InodeType::BlockDevice
+                } else {
+                    InodeType::CharDevice
+                };
+                (type_, raw.nlink, 0, InodeData::Device(raw.device))
+            }
+            6 | 13 | 7 | 14 => {
+                let nlink = fs.read_metadata_val::<u32>(&mut pos)?;
+                let type_ = if header.type_ % 7 == 6 {
+                    InodeType::NamedPipe
+                } else {
+                    InodeType::Socket
+                };
+                (type_, nlink, 0, InodeData::Ipc)
+            }
+            _ => return_errno_with_message!(Errno::EIO, "invalid squashfs inode type"),
+        };
+
+        let inode = Arc::new_cyclic(|weak_self: &Weak<Self>| Self {
+            ino: header.ino,
+            type_,
+            mode: header.mode & 0o7777,
+            uid: Uid::new(fs.id(header.uid_idx).unwrap_or(0)),
+            gid: Gid::new(fs.id(header.gid_idx).unwrap_or(0)),
+            mtime: Duration::from_secs(header.mtime as u64),
+            nlink,
+            size,
+            data,
+            page_cache: (type_ == InodeType::File)
+                .then(|| PageCache::with_capacity(size, weak_self.clone() as _).unwrap()),
+            fs: Arc::downgrade(fs),
+            extension: Extension::new(),
+        });
+        Ok(fs.cache_inode(inode))
+    }
+
+    pub(super) fn squash_ino(&self) -> u32 {
+        self.ino
+    }
+
+    fn fs(&self) -> Arc<SquashFs> {
+        self.fs.upgrade().unwrap()
+    }
+
+    fn dir_entries(&self) -> Result<(&[DirEntry], u32)> {
+        match &self.data {
+            InodeData::Dir {
+                entries,
+                parent_ino,
+            } => Ok((entries, *parent_ino)),
+            _ => return_errno!(Errno::ENOTDIR),
+        }
+    }
+
+    /// Returns an error for the modifications of a directory.
+    fn modify_dir(&self) -> Result<()> {
+        self.dir_entries()?;
+        return_errno_with_message!(Errno::EROFS, "squashfs is read-only")
+    }
+
+    fn read_page(&self, idx: usize, frame: &CachePage) -> Result<()> {
+        let InodeData::File { blocks, fragment } = &self.data else {
+            return_errno!(Errno::EINVAL);
+        };
+        let start = idx * PAGE_SIZE;
+        if start >= self.size {
+            return_errno_with_message!(Errno::EINVAL, "the page is beyond the file");
+        }
+
+        // A page is always within a block, as a block is at least one page.
+        let fs = self.fs();
+        let block_size = fs.super_block().block_size;
+        let block = match blocks.get(start / block_size) {
+            Some(&(_, size)) if size & !UNCOMPRESSED_BLOCK == 0 => None,
+            Some(&(location, size)) => Some((fs.read_data_block(location, size)?, 0)),
+            None => {
+                let (fragment_idx, fragment_offset) = fragment
+                    .ok_or_else(|| Error::with_message(Errno::EIO, "the file has no fragment"))?;
+                let (location, size) = fs.fragment(fragment_idx)?;
+                Some((
+                    fs.read_data_block(location, size)?,
+                    fragment_offset as usize,
+                ))
+            }
+        };
+
+        let mut buf = vec![0u8; PAGE_SIZE];
+        if let Some((data, base)) = block {
+            let len = PAGE_SIZE.min(self.size - start);
+            let data_start = base + start % block_size;
+            let data = data
+                .get(data_start..data_start + len)
+                .ok_or_else(|| Error::with_message(Errno::EIO, "the data block is too short"))?;
+            buf[..len].copy_from_slice(data);
+        }
+        frame.writer().write(&mut VmReader::from(buf.as_slice()));
+        Ok(())
+    }
+
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let Some(page_cache) = self.page_cache.as_ref() else {
+            return_errno!(Errno::EISDIR);
+        };
+
+        let start = self.size.min(offset);
+        let end = self.size.min(offset + writer.avail());
+        page_cache.pages().read(start, writer)?;
+        Ok(end - start)
+    }
+}
+
+/// Reads the entries of a directory listing, whose size includes three extra bytes.
+fn read_dir_entries(
+    fs: &SquashFs,
+    block_index: u32,
+    block_offset: u16,
+    size: usize,
+) -> Result<Vec<DirEntry>> {
+    let mut pos = MetadataPos {
+        block: fs.super_block().dir_table + block_index as u64,
+        offset: block_offset as usize,
+    };
+    let mut remaining = size.saturating_sub(3);
+    let mut entries = Vec::new();
+    let mut consume = |len: usize| -> Result<()> {
+        remaining = remaining
+            .checked_sub(len)
+            .ok_or_else(|| Error::with_message(Errno::EIO, "corrupted squashfs directory"))?;
+        Ok(())
+    };
+
+    while remaining > 0 {
+        let header = fs.read_metadata_val::<RawDirHeader>(&mut pos)?;
+        consume(size_of::<RawDirHeader>())?;
+        if header.count >= 256 {
+            return_errno_with_message!(Errno::EIO, "corrupted squashfs directory");
+        }
+
+        for _ in 0..=header.count {
+            let raw = fs.read_metadata_val::<RawDirEntry>(&mut pos)?;
+            let mut name = vec![0u8; raw.name_size as usize + 1];
+            fs.read_metadata(&mut pos, &mut name)?;
+            consume(size_of::<RawDirEntry>() + name.len())?;
+
+            let type_ = match raw.type_ {
+                1 => InodeType::Dir,
+                2 => InodeType::File,
+                3 => InodeType::SymLink,
+                4 => InodeType::BlockDevice,
+                5 => InodeType::CharDevice,
+                6 => InodeType::NamedPipe,
+                7 => InodeType::Socket,
+                _ => return_errno_with_message!(Errno::EIO, "invalid squashfs entry type"),
+            };
+            entries.push(DirEntry {
+                name: String::from_utf8_lossy(&name).into_owned(),
+                inode_ref: ((header.start as u64) << 16) | raw.offset as u64,
+                ino: header.ino.wrapping_add_signed(raw.ino_delta as i32),
+                type_,
+            });
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Reads the block list of a regular file, which follows the inode.
+fn read_file_layout(
+    fs: &SquashFs,
+    pos: &mut MetadataPos,
+    blocks_start: u64,
+    size: usize,
+    fragment_idx: u32,
+    fragment_offset: u32,
+) -> Result<InodeData> {
+    let block_size = fs.super_block().block_size;
+    let fragment = (fragment_idx != NO_FRAGMENT).then_some((fragment_idx, fragment_offset));
+    // The tail that is smaller than a block is stored in a fragment if there is one.
+    let nr_blocks = if fragment.is_some() {
+        size / block_size
+    } else {
+        size.div_ceil(block_size)
+    };
+
+    let mut blocks = Vec::new();
+    let mut location = blocks_start;
+    for _ in 0..nr_blocks {
+        let size = fs.read_metadata_val::<u32>(pos)?;
+        blocks.push((location, size));
+        location += (size & !UNCOMPRESSED_BLOCK) as u64;
+    }
+
+    Ok(InodeData::File { blocks, fragment })
+}
+
+impl PageCacheBackend for SquashInode {
+    fn read_page_async(&self, idx: usize, frame: &CachePage) -> Result<BioWaiter> {
+        self.read_page(idx, frame)?;
+        Ok(BioWaiter::new())
+    }
+
+    fn write_page_async(&self, _idx: usize, _frame: &CachePage) -> Result<BioWaiter> {
+        return_errno_with_message!(Errno::EROFS, "squashfs is read-only")
+    }
+
+    fn npages(&self) -> usize {
+        self.size.div_ceil(PAGE_SIZE)
+    }
+}
+
+impl InodeIo for SquashInode {
+    fn read_at(
+        &self,
+        offset: usize,
+        writer: &mut VmWriter,
+        _status_flags: StatusFlags,
+    ) -> Result<usize> {
+        self.read_at(offset, writer)
+    }
+
+    fn write_at(
+        &self,
+        _offset: usize,
+        _reader: &mut VmReader,
+        _status_flags: StatusFlags,
+    ) -> Result<usize> {
+        return_errno_with_message!(Errno::EROFS, "squashfs is read-only")
+    }
+}
+
+impl Inode for SquashInode {
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn resize(&self, _new_size: usize) -> Result<()> {
+        return_errno_with_message!(Errno::EROFS, "squashfs is read-only")
+    }
+
+    fn metadata(&self) -> Metadata {
+        let fs = self.fs();
+        Metadata {
+            ino: self.ino as u64,
+            size: self.size,
+            optimal_block_size: fs.super_block().block_size,
+            nr_sectors_allocated: self.size.div_ceil(SECTOR_SIZE),
+            last_access_at: self.mtime,
+            last_modify_at: self.mtime,
+            last_meta_change_at: self.mtime,
+            type_: self.type_,
+            mode: InodeMode::from_bits_truncate(self.mode),
+            nr_hard_links: self.nlink as usize,
+            uid: self.uid,
+            gid: self.gid,
+            container_dev_id: fs.block_device().id(),
+            self_dev_id: match self.data {
+                InodeData::Device(device) => DeviceId::from_encoded_u64(device as u64),
+                _ => None,
+            },
+        }
+    }
+
+    fn ino(&self) -> u64 {
+        self.ino as u64
+    }
+
+    fn type_(&self) -> InodeType {
+        self.type_
+    }
+
+    fn mode(&self) -> Result<InodeMode> {
+        Ok(InodeMode::from_bits_truncate(self.mode))
+    }
+
+    fn set_mode(&self, _mode: InodeMode) -> Result<()> {
+        return_errno_with_message!(Errno::EROFS, "squashfs is read-only")
+    }
+
+    fn owner(&self) -> Result<Uid> {
+        Ok(self.uid)
+    }
+
+    fn set_owner(&self, _uid: Uid) -> Result<()> {
+        return_errno_with_message!(Errno::EROFS, "squashfs is read-only")
+    }
+
+    fn group(&self) -> Result<Gid> {
+        Ok(self.gid)
+    }
+
+    fn set_group(&self, _gid: Gid) -> Result<()> {
+        return_errno_with_message!(Errno::EROFS, "squashfs is read-only")
+    }
+
+    fn atime(&self) -> Duration {
+        self.mtime
+    }
+
+    fn set_atime(&self, _time: Duration) {}
+
+    fn mtime(&self) -> Duration {
+        self.mtime
+    }
+
+    fn set_mtime(&self, _time: Duration) {}
+
+    fn ctime(&self) -> Duration {
+        self.mtime
+    }
+
+    fn set_ctime(&self, _time: Duration) {}
+
+    fn page_cache(&self) -> Option<Arc<Vmo>> {
+        self.page_cache
+            .as_ref()
+            .map(|page_cache| page_cache.pages().clone())
+    }
+
+    fn create(&self, _name: &str, _type_: InodeType, _mode: InodeMode) -> Result<Arc<dyn Inode>> {
+        self.modify_dir()?;
+        unreachable!()
+    }
+
+    fn mknod(&self, _name: &str, _mode: InodeMode, _type_: MknodType) -> Result<Arc<dyn Inode>> {
+        self.modify_dir()?;
+        unreachable!()
+    }
+
+    fn readdir_at(&self, offset: usize, visitor: &mut dyn DirentVisitor) -> Result<usize> {
+        let (entries, parent_ino) = self.dir_entries()?;
+        // The parent of the root directory is out of the range of the inode numbers.
+        let parent_ino = if parent_ino > self.fs().super_block().inode_count {
+            self.ino
+        } else {
+            parent_ino
+        };
+
+        // The offsets 0 and 1 are `.` and `..`, and the offset `n + 2` is the entry `n`.
+        let try_readdir = |offset: &mut usize, visitor: &mut dyn DirentVisitor| -> Result<()> {
+            if *offset == 0 {
+                visitor.visit(".", self.ino as u64, InodeType::Dir, 1)?;
+                *offset = 1;
+            }
+            if *offset == 1 {
+                visitor.visit("..", parent_ino as u64, InodeType::Dir, 2)?;
+                *offset = 2;
+            }
+            for (idx, entry) in entries.iter().enumerate().skip(*offset - 2) {
+                visitor.visit(&entry.name, entry.ino as u64, entry.type_, idx + 3)?;
+                *offset = idx + 3;
+            }
+            Ok(())
+        };
+
+        let mut iterate_offset = offset;
+        match try_readdir(&mut iterate_offset, visitor) {
+            Err(e) if iterate_offset == offset => Err(e),
+            _ => Ok(iterate_offset - offset),
+        }
+    }
+
+    fn link(&self, _old: &Arc<dyn Inode>, _name: &str) -> Result<()> {
+        self.modify_dir()
+    }
+
+    fn unlink(&self, _name: &str) -> Result<()> {
+        self.modify_dir()
+    }
+
+    fn rmdir(&self, _name: &str) -> Result<()> {
+        self.modify_dir()
+    }
+
+    fn lookup(&self, name: &str) -> Result<Arc<dyn Inode>> {
+        let (entries, _) = self.dir_entries()?;
+        let entry = entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .ok_or_else(|| Error::new(Errno::ENOENT))?;
+        Ok(SquashInode::load(&self.fs(), entry.inode_ref)?)
+    }
+
+    fn rename(&self, _old_name: &str, _target: &Arc<dyn Inode>, _new_name: &str) -> Result<()> {
+        self.modify_dir()
+    }
+
+    fn read_link(&self) -> Result<SymbolicLink> {
+        match &self.data {
+            InodeData::Symlink(target) => Ok(SymbolicLink::Plain(target.clone())),
+            _ => return_errno!(Errno::EINVAL),
+        }
+    }
+
+    fn write_link(&self, _target: &str) -> Result<()> {
+        return_errno_with_message!(Errno::EROFS, "squashfs is read-only")
+    }
+
+    fn fs(&self) -> Arc<dyn FileSystem> {
+        self.fs()
+    }
+
+    fn extension(&self) -> &Extension {
+        &self.extension
+    }
+}
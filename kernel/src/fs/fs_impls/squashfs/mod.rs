@@ -0,0 +1,477 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! A read-only squashfs 4.0 filesystem.
+//!
+//! Squashfs is the compressed filesystem that many container images and live-boot
+//! root filesystems ship as.
+//!
+//! The features of this version of squashfs are as follows:
+//! 1. Zlib and zstd compression. The metadata blocks and the data blocks are
+//!    decompressed on demand, and the recently used ones are kept in LRU caches.
+//! 2. Fragments. The tails of the files that are smaller than a block are read from
+//!    the shared fragment blocks.
+//! 3. Sparse files. The data blocks whose sizes are zero are read as zeros.
+//! 4. Deep integration with PageCache. The data of regular files are read through
+//!    the page caches of the inodes.
+//!
+//! # Limitation
+//!
+//! Here we summarizes the features that need to be implemented in the future.
+//! 1. Supports the compressors other than zlib and zstd, e.g., LZ4, LZO and XZ.
+//! 2. Supports extended attributes.
+//! 3. Uses the directory indexes to speed up the lookups in large directories.
+
+pub use fs::SquashFs;
+
+use crate::fs::squashfs::fs::SquashType;
+
+mod compressor;
+mod fs;
+mod inode;
+mod super_block;
+mod zstd;
+
+pub(super) fn init() {
+    crate::fs::vfs::registry::register(&SquashType).unwrap();
+}
+
+#[cfg(ktest)]
+mod test {
+    use alloc::fmt::Debug;
+
+    use aster_block::{
+        BlockDevice, BlockDeviceMeta, SECTOR_SIZE,
+        bio::{BioEnqueueError, BioStatus, BioType, SubmittedBio},
+    };
+    use device_id::DeviceId;
+    use ostd::{
+        mm::{FrameAllocOptions, PAGE_SIZE, Segment, VmIo, io::util::HasVmReaderWriter},
+        prelude::*,
+    };
+
+    use super::fs::SquashFs;
+    use crate::{
+        fs::{
+            file::{InodeMode, InodeType},
+            vfs::{
+                file_system::FileSystem,
+                inode::{Inode, SymbolicLink},
+            },
+        },
+        prelude::*,
+    };
+
+    /// A read-only block device whose sectors are kept in memory.
+    struct SquashMemoryDisk(Segment<()>);
+
+    impl Debug for SquashMemoryDisk {
+        fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+            f.debug_struct("SquashMemoryDisk")
+                .field("sectors_count", &(self.0.size() / SECTOR_SIZE))
+                .finish()
+        }
+    }
+
+    impl BlockDevice for SquashMemoryDisk {
+        fn enqueue(&self, bio: SubmittedBio) -> core::prelude::v1::Result<(), BioEnqueueError> {
+            let mut cur_device_ofs = bio.sid_range().start.to_raw() as usize * SECTOR_SIZE;
+            for seg in bio.segments() {
+                let size = match bio.type_() {
+                    BioType::Read => seg
+                        .inner_dma()
+                        .writer()
+                        .unwrap()
+                        .write(self.0.reader().skip(cur_device_ofs)),
+                    _ => 0,
+                };
+                cur_device_ofs += size;
+            }
+            bio.complete(BioStatus::Complete);
+            Ok(())
+        }
+
+        fn metadata(&self) -> BlockDeviceMeta {
+            BlockDeviceMeta {
+                max_nr_segments_per_bio: usize::MAX,
+                nr_sectors: self.0.size() / SECTOR_SIZE,
+                supports_fua: false,
+                max_discard_sectors: 0,
+            }
+        }
+
+        fn name(&self) -> &str {
+            todo!()
+        }
+
+        fn id(&self) -> DeviceId {
+            todo!()
+        }
+    }
+
+    const BLOCK_SIZE: usize = 4096;
+    const HELLO_LEN: usize = 5000;
+    const LINK_TARGET: &str = "hello.txt";
+
+    const NO_FRAGMENT: u32 = 0xFFFF_FFFF;
+    const UNCOMPRESSED_BLOCK: u32 = 1 << 24;
+    const UNCOMPRESSED_METADATA: u16 = 0x8000;
+
+    /// The inode numbers of the test image.
+    const HELLO_INO: u32 = 1;
+    const LINK_INO: u32 = 2;
+    const EMPTY_INO: u32 = 3;
+    const SUB_INO: u32 = 4;
+    const ROOT_INO: u32 = 5;
+
+    /// The offsets of the fields of the superblock.
+    const ROOT_INODE_OFFSET: usize = 32;
+    const INODE_TABLE_OFFSET: usize = 64;
+    const DIR_TABLE_OFFSET: usize = 72;
+
+    /// The content of `hello.txt`, whose first block is compressed and whose tail is
+    /// in a fragment.
+    fn hello_data() -> Vec<u8> {
+        (0..HELLO_LEN).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn push_u16(buf: &mut Vec<u8>, value: u16) {
+        buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn push_u32(buf: &mut Vec<u8>, value: u32) {
+        buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn push_u64(buf: &mut Vec<u8>, value: u64) {
+        buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// Compresses the data into a zlib stream of a stored deflate block.
+    fn zlib_stored(data: &[u8]) -> Vec<u8> {
+        let len = data.len() as u16;
+        let mut stream = vec![0x78, 0x01, 0x01];
+        push_u16(&mut stream, len);
+        push_u16(&mut stream, !len);
+        stream.extend_from_slice(data);
+
+        let (mut a, mut b) = (1u32, 0u32);
+        for &byte in data {
+            a = (a + byte as u32) % 65521;
+            b = (b + a) % 65521;
+        }
+        stream.extend_from_slice(&((b << 16) | a).to_be_bytes());
+        stream
+    }
+
+    /// Appends an uncompressed metadata block and returns its location.
+    fn push_metadata(image: &mut Vec<u8>, data: &[u8]) -> u64 {
+        let location = image.len() as u64;
+        push_u16(image, data.len() as u16 | UNCOMPRESSED_METADATA);
+        image.extend_from_slice(data);
+        location
+    }
+
+    /// Appends the common header of an inode and returns the offset of the inode.
+    fn push_inode_header(inodes: &mut Vec<u8>, type_: u16, mode: u16, ino: u32) -> u16 {
+        let offset = inodes.len() as u16;
+        for value in [type_, mode, 0, 0] {
+            push_u16(inodes, value);
+        }
+        push_u32(inodes, 0);
+        push_u32(inodes, ino);
+        offset
+    }
+
+    /// Appends a basic directory inode and returns its offset.
+    fn push_dir_inode(
+        inodes: &mut Vec<u8>,
+        ino: u32,
+        listing_offset: usize,
+        listing_len: usize,
+        parent_ino: u32,
+    ) -> u16 {
+        let offset = push_inode_header(inodes, 1, 0o755, ino);
+        push_u32(inodes, 0);
+        push_u32(inodes, 2);
+        push_u16(inodes, listing_len as u16 + 3);
+        push_u16(inodes, listing_offset as u16);
+        push_u32(inodes, parent_ino);
+        offset
+    }
+
+    /// Appends a directory listing whose entries are in the first inode metadata block.
+    fn push_listing(listing: &mut Vec<u8>, entries: &[(&str, u16, u32, u16)]) {
+        let base_ino = entries[0].2;
+        push_u32(listing, entries.len() as u32 - 1);
+        push_u32(listing, 0);
+        push_u32(listing, base_ino);
+        for &(name, offset, ino, type_) in entries {
+            push_u16(listing, offset);
+            push_u16(listing, (ino as i32 - base_ino as i32) as i16 as u16);
+            push_u16(listing, type_);
+            push_u16(listing, name.len() as u16 - 1);
+            listing.extend_from_slice(name.as_bytes());
+        }
+    }
+
+    /// Builds an image with `hello.txt`, `link` and `sub` in the root directory, where
+    /// `link` points to `hello.txt` and `sub` contains an empty file `empty`.
+    fn build_image() -> Vec<u8> {
+        let hello = hello_data();
+        let mut image = vec![0u8; 96];
+
+        let block_location = image.len() as u64;
+        let block = zlib_stored(&hello[..BLOCK_SIZE]);
+        image.extend_from_slice(&block);
+        let fragment_location = image.len() as u64;
+        image.extend_from_slice(&hello[BLOCK_SIZE..]);
+
+        let mut inodes = Vec::new();
+        let hello_offset = push_inode_header(&mut inodes, 2, 0o644, HELLO_INO);
+        push_u32(&mut inodes, block_location as u32);
+        push_u32(&mut inodes, 0);
+        push_u32(&mut inodes, 0);
+        push_u32(&mut inodes, HELLO_LEN as u32);
+        push_u32(&mut inodes, block.len() as u32);
+
+        let link_offset = push_inode_header(&mut inodes, 3, 0o777, LINK_INO);
+        push_u32(&mut inodes, 1);
+        push_u32(&mut inodes, LINK_TARGET.len() as u32);
+        inodes.extend_from_slice(LINK_TARGET.as_bytes());
+
+        let empty_offset = push_inode_header(&mut inodes, 2, 0o600, EMPTY_INO);
+        push_u32(&mut inodes, 0);
+        push_u32(&mut inodes, NO_FRAGMENT);
+        push_u32(&mut inodes, 0);
+        push_u32(&mut inodes, 0);
+
+        let mut listings = Vec::new();
+        push_listing(&mut listings, &[("empty", empty_offset, EMPTY_INO, 2)]);
+        let sub_listing_len = listings.len();
+        let sub_offset = push_dir_inode(&mut inodes, SUB_INO, 0, sub_listing_len, ROOT_INO);
+
+        push_listing(
+            &mut listings,
+            &[
+                ("hello.txt", hello_offset, HELLO_INO, 2),
+                ("link", link_offset, LINK_INO, 3),
+                ("sub", sub_offset, SUB_INO, 1),
+            ],
+        );
+        let root_listing_len = listings.len() - sub_listing_len;
+        let root_offset = push_dir_inode(
+            &mut inodes,
+            ROOT_INO,
+            sub_listing_len,
+            root_listing_len,
+            ROOT_INO + 1,
+        );
+
+        let inode_table = push_metadata(&mut image, &inodes);
+        let dir_table = push_metadata(&mut image, &listings);
+
+        let mut fragment_entry = Vec::new();
+        push_u64(&mut fragment_entry, fragment_location);
+        push_u32(
+            &mut fragment_entry,
+            (HELLO_LEN - BLOCK_SIZE) as u32 | UNCOMPRESSED_BLOCK,
+        );
+        push_u32(&mut fragment_entry, 0);
+        let fragment_block = push_metadata(&mut image, &fragment_entry);
+        let fragment_table = image.len() as u64;
+        push_u64(&mut image, fragment_block);
+
+        let id_block = push_metadata(&mut image, &0u32.to_le_bytes());
+        let id_table = image.len() as u64;
+        push_u64(&mut image, id_block);
+
+        let mut super_block = Vec::new();
+        push_u32(&mut super_block, 0x7371_7368);
+        push_u32(&mut super_block, ROOT_INO);
+        push_u32(&mut super_block, 0);
+        push_u32(&mut super_block, BLOCK_SIZE as u32);
+        push_u32(&mut super_block, 1);
+        // Zlib, the block size of 2^12, no flags, one ID and version 4.0.
+        for value in [1, 12, 0, 1, 4, 0] {
+            push_u16(&mut super_block, value);
+        }
+        push_u64(&mut super_block, root_offset as u64);
+        push_u64(&mut super_block, image.len() as u64);
+        for table in [
+            id_table,
+            u64::MAX,
+            inode_table,
+            dir_table,
+            fragment_table,
+            u64::MAX,
+        ] {
+            push_u64(&mut super_block, table);
+        }
+        image[..96].copy_from_slice(&super_block);
+
+        image
+    }
+
+    fn open(image: &[u8]) -> Result<Arc<SquashFs>> {
+        let segment = FrameAllocOptions::new()
+            .alloc_segment(image.len().div_ceil(PAGE_SIZE))
+            .unwrap();
+        segment.write_bytes(0, image).unwrap();
+        SquashFs::open(Arc::new(SquashMemoryDisk(segment)))
+    }
+
+    fn read_u64(image: &[u8], offset: usize) -> usize {
+        u64::from_le_bytes(image[offset..offset + 8].try_into().unwrap()) as usize
+    }
+
+    /// Returns the offset of the data of the first inode metadata block in the image.
+    fn inode_data(image: &[u8]) -> usize {
+        read_u64(image, INODE_TABLE_OFFSET) + 2
+    }
+
+    fn list_names(dir: &Arc<dyn Inode>) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        dir.readdir_at(0, &mut names).unwrap();
+        names
+    }
+
+    #[ktest]
+    fn lookup_and_read() {
+        let fs = open(&build_image()).unwrap();
+        let root = fs.root_inode();
+        assert_eq!(root.ino(), ROOT_INO as u64);
+        assert_eq!(list_names(&root), [".", "..", "hello.txt", "link", "sub"]);
+
+        let hello = root.lookup("hello.txt").unwrap();
+        assert_eq!(hello.type_(), InodeType::File);
+        assert_eq!(hello.mode().unwrap().bits(), 0o644);
+        let mut buf = vec![0u8; HELLO_LEN];
+        assert_eq!(hello.read_bytes_at(0, &mut buf).unwrap(), HELLO_LEN);
+        assert_eq!(buf, hello_data());
+
+        // The tail is read from the fragment.
+        let mut buf = vec![0u8; 100];
+        assert_eq!(hello.read_bytes_at(HELLO_LEN - 10, &mut buf).unwrap(), 10);
+        assert_eq!(buf[..10], hello_data()[HELLO_LEN - 10..]);
+
+        let link = root.lookup("link").unwrap();
+        assert_eq!(link.type_(), InodeType::SymLink);
+        assert!(matches!(
+            link.read_link().unwrap(),
+            SymbolicLink::Plain(target) if target == LINK_TARGET
+        ));
+
+        let sub = root.lookup("sub").unwrap();
+        assert_eq!(sub.type_(), InodeType::Dir);
+        assert_eq!(list_names(&sub), [".", "..", "empty"]);
+        let empty = sub.lookup("empty").unwrap();
+        assert_eq!(empty.size(), 0);
+        assert_eq!(empty.read_bytes_at(0, &mut buf).unwrap(), 0);
+
+        assert_eq!(root.lookup("missing").unwrap_err().error(), Errno::ENOENT);
+        assert_eq!(
+            root.create("new", InodeType::File, InodeMode::all())
+                .unwrap_err()
+                .error(),
+            Errno::EROFS
+        );
+    }
+
+    #[ktest]
+    fn malformed_super_block() {
+        let corrupt = |offset: usize, bytes: &[u8]| {
+            let mut image = build_image();
+            image[offset..offset + bytes.len()].copy_from_slice(bytes);
+            open(&image).unwrap_err().error()
+        };
+
+        assert_eq!(corrupt(0, b"sqsh"), Errno::EINVAL);
+        // An LZO image, and an unknown compressor.
+        assert_eq!(corrupt(20, &3u16.to_le_bytes()), Errno::EINVAL);
+        assert_eq!(corrupt(20, &100u16.to_le_bytes()), Errno::EINVAL);
+        // The block size does not match its logarithm.
+        assert_eq!(corrupt(22, &13u16.to_le_bytes()), Errno::EINVAL);
+        assert_eq!(corrupt(12, &1024u32.to_le_bytes()), Errno::EINVAL);
+        assert_eq!(corrupt(28, &3u16.to_le_bytes()), Errno::EINVAL);
+        // The image is larger than the device.
+        assert_eq!(corrupt(40, &u64::MAX.to_le_bytes()), Errno::EINVAL);
+        // The tables are beyond the image, or the inode table follows the directory table.
+        assert_eq!(corrupt(48, &u64::MAX.to_le_bytes()), Errno::EINVAL);
+        assert_eq!(
+            corrupt(INODE_TABLE_OFFSET, &u64::MAX.to_le_bytes()),
+            Errno::EINVAL
+        );
+        let image = build_image();
+        let dir_table = read_u64(&image, DIR_TABLE_OFFSET) as u64;
+        assert_eq!(
+            corrupt(INODE_TABLE_OFFSET, &dir_table.to_le_bytes()),
+            Errno::EINVAL
+        );
+        assert_eq!(corrupt(80, &(u64::MAX - 1).to_le_bytes()), Errno::EINVAL);
+    }
+
+    #[ktest]
+    fn malformed_inode_table() {
+        let image = build_image();
+        let inodes = inode_data(&image);
+        let root_offset = read_u64(&image, ROOT_INODE_OFFSET);
+
+        // The metadata block is empty, or larger than a metadata block can be.
+        let mut corrupted = image.clone();
+        corrupted[inodes - 2..inodes].copy_from_slice(&0x8000u16.to_le_bytes());
+        assert_eq!(open(&corrupted).unwrap_err().error(), Errno::EIO);
+        corrupted[inodes - 2..inodes].copy_from_slice(&0xA001u16.to_le_bytes());
+        assert_eq!(open(&corrupted).unwrap_err().error(), Errno::EIO);
+
+        // The metadata block is compressed, but is not a zlib stream.
+        let mut corrupted = image.clone();
+        corrupted[inodes - 1] &= !0x80;
+        assert_eq!(open(&corrupted).unwrap_err().error(), Errno::EIO);
+
+        // The root inode is beyond its metadata block.
+        let mut corrupted = image.clone();
+        corrupted[ROOT_INODE_OFFSET..ROOT_INODE_OFFSET + 8]
+            .copy_from_slice(&0xFFFFu64.to_le_bytes());
+        assert_eq!(open(&corrupted).unwrap_err().error(), Errno::EIO);
+
+        // The root inode has an invalid type, or is a regular file.
+        let mut corrupted = image.clone();
+        corrupted[inodes + root_offset..][..2].copy_from_slice(&99u16.to_le_bytes());
+        assert_eq!(open(&corrupted).unwrap_err().error(), Errno::EIO);
+        let mut corrupted = image.clone();
+        corrupted[ROOT_INODE_OFFSET..ROOT_INODE_OFFSET + 8].copy_from_slice(&0u64.to_le_bytes());
+        assert_eq!(open(&corrupted).unwrap_err().error(), Errno::EINVAL);
+
+        // The root directory claims more entries than a header can have.
+        let mut corrupted = image.clone();
+        let listings = read_u64(&image, DIR_TABLE_OFFSET) + 2;
+        let root_listing =
+            u16::from_le_bytes(image[inodes + root_offset + 26..][..2].try_into().unwrap())
+                as usize;
+        corrupted[listings + root_listing..][..4].copy_from_slice(&1000u32.to_le_bytes());
+        assert_eq!(open(&corrupted).unwrap_err().error(), Errno::EIO);
+
+        // The root directory is larger than its listing.
+        let mut corrupted = image.clone();
+        corrupted[inodes + root_offset + 24..][..2].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert_eq!(open(&corrupted).unwrap_err().error(), Errno::EIO);
+
+        // `hello.txt` is so large that its block list runs past the end of the image,
+        // which fails the lookup only.
+        let mut corrupted = image.clone();
+        corrupted[inodes + 28..][..4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let fs = open(&corrupted).unwrap();
+        assert_eq!(
+            fs.root_inode().lookup("hello.txt").unwrap_err().error(),
+            Errno::EIO
+        );
+
+        // The fragment of `hello.txt` does not exist.
+        let mut corrupted = image.clone();
+        corrupted[inodes + 20..][..4].copy_from_slice(&1u32.to_le_bytes());
+        let fs = open(&corrupted).unwrap();
+        let hello = fs.root_inode().lookup("hello.txt").unwrap();
+        let mut buf = vec![0u8; HELLO_LEN];
+        assert!(hello.read_bytes_at(0, &mut buf).is_err());
+    }
+}
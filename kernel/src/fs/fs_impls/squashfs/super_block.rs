@@ -0,0 +1,119 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_block::BlockDevice;
+use ostd::{const_assert, mm::VmIo};
+
+use super::compressor::Compressor;
+use crate::prelude::*;
+
+/// The magic number of squashfs, i.e., "hsqs" in little endian.
+pub(super) const SQUASHFS_MAGIC: u32 = 0x7371_7368;
+
+const MIN_BLOCK_SIZE: u32 = 4096;
+const MAX_BLOCK_SIZE: u32 = 1024 * 1024;
+
+/// The size of the decompressed metadata blocks.
+pub(super) const METADATA_SIZE: usize = 8192;
+
+/// The value of the table locations that are absent.
+pub(super) const INVALID_TABLE: u64 = u64::MAX;
+
+/// The on-disk superblock of squashfs 4.0.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Pod)]
+struct RawSuperBlock {
+    magic: u32,
+    inode_count: u32,
+    mod_time: u32,
+    block_size: u32,
+    fragment_count: u32,
+    compressor: u16,
+    block_log: u16,
+    flags: u16,
+    id_count: u16,
+    version_major: u16,
+    version_minor: u16,
+    root_inode: u64,
+    bytes_used: u64,
+    id_table: u64,
+    xattr_table: u64,
+    inode_table: u64,
+    dir_table: u64,
+    fragment_table: u64,
+    export_table: u64,
+}
+
+const_assert!(size_of::<RawSuperBlock>() == 96);
+
+/// The superblock of squashfs, which gives the locations of the tables.
+///
+/// All the locations are byte offsets on the device.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct SquashSuperBlock {
+    pub(super) inode_count: u32,
+    pub(super) block_size: usize,
+    pub(super) fragment_count: u32,
+    pub(super) compressor: Compressor,
+    pub(super) id_count: u16,
+    /// The reference to the root inode.
+    pub(super) root_inode: u64,
+    pub(super) bytes_used: u64,
+    pub(super) id_table: u64,
+    pub(super) inode_table: u64,
+    pub(super) dir_table: u64,
+    pub(super) fragment_table: u64,
+}
+
+impl SquashSuperBlock {
+    /// Reads and validates the superblock.
+    pub(super) fn read(block_device: &dyn BlockDevice) -> Result<Self> {
+        let mut buf = [0u8; aster_block::SECTOR_SIZE];
+        block_device.read_bytes(0, &mut buf)?;
+        let raw = RawSuperBlock::from_bytes(&buf[..size_of::<RawSuperBlock>()]);
+
+        if raw.magic != SQUASHFS_MAGIC {
+            return_errno_with_message!(Errno::EINVAL, "invalid squashfs magic");
+        }
+        if raw.version_major != 4 || raw.version_minor != 0 {
+            return_errno_with_message!(Errno::EINVAL, "unsupported squashfs version");
+        }
+        if !raw.block_size.is_power_of_two()
+            || !(MIN_BLOCK_SIZE..=MAX_BLOCK_SIZE).contains(&raw.block_size)
+            || raw.block_size.trailing_zeros() != raw.block_log as u32
+        {
+            return_errno_with_message!(Errno::EINVAL, "invalid squashfs block size");
+        }
+        let device_size = (block_device.metadata().nr_sectors * aster_block::SECTOR_SIZE) as u64;
+        if raw.bytes_used > device_size {
+            return_errno_with_message!(Errno::EINVAL, "the device is too small");
+        }
+        let compressor = Compressor::from_id(raw.compressor)?;
+
+        for table in [raw.id_table, raw.inode_table, raw.dir_table] {
+            if table >= raw.bytes_used {
+                return_errno_with_message!(Errno::EINVAL, "invalid squashfs table location");
+            }
+        }
+        if raw.inode_table >= raw.dir_table {
+            return_errno_with_message!(Errno::EINVAL, "invalid squashfs table location");
+        }
+        let has_fragments = raw.fragment_count != 0 && raw.fragment_table != INVALID_TABLE;
+        if has_fragments && raw.fragment_table >= raw.bytes_used {
+            return_errno_with_message!(Errno::EINVAL, "invalid squashfs fragment table");
+        }
+
+        Ok(Self {
+            inode_count: raw.inode_count,
+            block_size: raw.block_size as usize,
+            fragment_count: if has_fragments { raw.fragment_count } else { 0 },
+            compressor,
+            id_count: raw.id_count,
+            root_inode: raw.root_inode,
+            bytes_used: raw.bytes_used,
+            id_table: raw.id_table,
+            inode_table: raw.inode_table,
+            dir_table: raw.dir_table,
+            fragment_table: raw.fragment_table,
+        })
+    }
+}
@@ -0,0 +1,893 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! A decoder of Zstandard frames.
+//!
+//! Only what squashfs needs is supported: each block is a single frame that is
+//! decompressed as a whole, without dictionaries. The checksums are not verified.
+//!
+//! Reference: <https://datatracker.ietf.org/doc/html/rfc8878>
+
+use crate::prelude::*;
+
+const FRAME_MAGIC: u32 = 0xFD2F_B528;
+const SKIPPABLE_MAGIC_MASK: u32 = 0xFFFF_FFF0;
+const SKIPPABLE_MAGIC: u32 = 0x184D_2A50;
+
+const MAX_BLOCK_SIZE: usize = 128 * 1024;
+
+const MAX_HUFFMAN_BITS: u8 = 11;
+const MAX_LL_ACCURACY_LOG: u8 = 9;
+const MAX_ML_ACCURACY_LOG: u8 = 9;
+const MAX_OF_ACCURACY_LOG: u8 = 8;
+const MAX_HUFFMAN_WEIGHT_ACCURACY_LOG: u8 = 6;
+
+/// The baselines and the numbers of extra bits of the literals length codes.
+const LL_CODES: [(u32, u8); 36] = [
+    (0, 0),
+    (1, 0),
+    (2, 0),
+    (3, 0),
+    (4, 0),
+    (5, 0),
+    (6, 0),
+    (7, 0),
+    (8, 0),
+    (9, 0),
+    (10, 0),
+    (11, 0),
+    (12, 0),
+    (13, 0),
+    (14, 0),
+    (15, 0),
+    (16, 1),
+    (18, 1),
+    (20, 1),
+    (22, 1),
+    (24, 2),
+    (28, 2),
+    (32, 3),
+    (40, 3),
+    (48, 4),
+    (64, 6),
+    (128, 7),
+    (256, 8),
+    (512, 9),
+    (1024, 10),
+    (2048, 11),
+    (4096, 12),
+    (8192, 13),
+    (16384, 14),
+    (32768, 15),
+    (65536, 16),
+];
+
+/// The baselines and the numbers of extra bits of the match length codes.
+const ML_CODES: [(u32, u8); 53] = [
+    (3, 0),
+    (4, 0),
+    (5, 0),
+    (6, 0),
+    (7, 0),
+    (8, 0),
+    (9, 0),
+    (10, 0),
+    (11, 0),
+    (12, 0),
+    (13, 0),
+    (14, 0),
+    (15, 0),
+    (16, 0),
+    (17, 0),
+    (18, 0),
+    (19, 0),
+    (20, 0),
+    (21, 0),
+    (22, 0),
+    (23, 0),
+    (24, 0),
+    (25, 0),
+    (26, 0),
+    (27, 0),
+    (28, 0),
+    (29, 0),
+    (30, 0),
+    (31, 0),
+    (32, 0),
+    (33, 0),
+    (34, 0),
+    (35, 1),
+    (37, 1),
+    (39, 1),
+    (41, 1),
+    (43, 2),
+    (47, 2),
+    (51, 3),
+    (59, 3),
+    (67, 4),
+    (83, 4),
+    (99, 5),
+    (131, 7),
+    (259, 8),
+    (515, 9),
+    (1027, 10),
+    (2051, 11),
+    (4099, 12),
+    (8195, 13),
+    (16387, 14),
+    (32771, 15),
+    (65539, 16),
+];
+
+const MAX_OF_CODE: u8 = 31;
+
+const LL_DEFAULT_DISTRIBUTION: [i16; 36] = [
+    4, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 3, 2, 1, 1, 1, 1, 1,
+    -1, -1, -1, -1,
+];
+const LL_DEFAULT_ACCURACY_LOG: u8 = 6;
+
+const ML_DEFAULT_DISTRIBUTION: [i16; 53] = [
+    1, 4, 3, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1, -1, -1,
+];
+const ML_DEFAULT_ACCURACY_LOG: u8 = 6;
+
+const OF_DEFAULT_DISTRIBUTION: [i16; 29] = [
+    1, 1, 1, 1, 1, 1, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1,
+];
+const OF_DEFAULT_ACCURACY_LOG: u8 = 5;
+
+/// Decompresses the Zstandard frames in `src`, producing at most `max_len` bytes.
+pub(super) fn decompress(src: &[u8], max_len: usize) -> Result<Vec<u8>> {
+    let mut output = Vec::new();
+    let mut input = ForwardReader::new(src);
+    while !input.is_empty() {
+        let magic = input.read_u32()?;
+        if magic & SKIPPABLE_MAGIC_MASK == SKIPPABLE_MAGIC {
+            let len = input.read_u32()? as usize;
+            input.read_slice(len)?;
+            continue;
+        }
+        if magic != FRAME_MAGIC {
+            return Err(corrupted());
+        }
+        FrameDecoder::new(&mut output, max_len).decode(&mut input)?;
+    }
+    Ok(output)
+}
+
+fn corrupted() -> Error {
+    Error::with_message(Errno::EIO, "corrupted zstd data")
+}
+
+struct FrameDecoder<'a> {
+    output: &'a mut Vec<u8>,
+    /// The position where the frame starts in the output.
+    frame_start: usize,
+    max_len: usize,
+    huffman_table: Option<HuffmanTable>,
+    ll_table: Option<FseTable>,
+    ml_table: Option<FseTable>,
+    of_table: Option<FseTable>,
+    repeat_offsets: [usize; 3],
+}
+
+impl<'a> FrameDecoder<'a> {
+    fn new(output: &'a mut Vec<u8>, max_len: usize) -> Self {
+        let frame_start = output.len();
+        Self {
+            output,
+            frame_start,
+            max_len,
+            huffman_table: None,
+            ll_table: None,
+            ml_table: None,
+            of_table: None,
+            repeat_offsets: [1, 4, 8],
+        }
+    }
+
+    fn decode(&mut self, input: &mut ForwardReader) -> Result<()> {
+        let descriptor = input.read_u8()?;
+        let fcs_flag = descriptor >> 6;
+        let is_single_segment = descriptor & 0x20 != 0;
+        let has_checksum = descriptor & 0x04 != 0;
+        let dict_id_len = match descriptor & 0x3 {
+            0 => 0,
+            1 => 1,
+            2 => 2,
+            _ => 4,
+        };
+        if descriptor & 0x08 != 0 {
+            return Err(corrupted());
+        }
+
+        if !is_single_segment {
+            // The window size is not needed as the whole frame is kept in memory.
+            input.read_u8()?;
+        }
+        if input.read_le(dict_id_len)? != 0 {
+            return_errno_with_message!(Errno::EOPNOTSUPP, "zstd dictionaries are not supported");
+        }
+        let fcs_len = match (fcs_flag, is_single_segment) {
+            (0, false) => 0,
+            (0, true) => 1,
+            (1, _) => 2,
+            (2, _) => 4,
+            _ => 8,
+        };
+        input.read_le(fcs_len)?;
+
+        loop {
+            let header = input.read_le(3)? as u32;
+            let is_last = header & 1 != 0;
+            let block_size = (header >> 3) as usize;
+            if block_size > MAX_BLOCK_SIZE {
+                return Err(corrupted());
+            }
+
+            match (header >> 1) & 0x3 {
+                0 => {
+                    let data = input.read_slice(block_size)?;
+                    self.reserve(block_size)?;
+                    self.output.extend_from_slice(data);
+                }
+                1 => {
+                    let byte = input.read_u8()?;
+                    self.reserve(block_size)?;
+                    self.output.resize(self.output.len() + block_size, byte);
+                }
+                2 => {
+                    let data = input.read_slice(block_size)?;
+                    self.decode_compressed_block(data)?;
+                }
+                _ => return Err(corrupted()),
+            }
+
+            if is_last {
+                break;
+            }
+        }
+
+        if has_checksum {
+            input.read_u32()?;
+        }
+        Ok(())
+    }
+
+    fn reserve(&self, len: usize) -> Result<()> {
+        if self.output.len() + len > self.max_len {
+            return_errno_with_message!(Errno::EIO, "the zstd output is too large");
+        }
+        Ok(())
+    }
+
+    fn decode_compressed_block(&mut self, data: &[u8]) -> Result<()> {
+        let mut input = ForwardReader::new(data);
+        let literals = self.decode_literals(&mut input)?;
+        self.decode_sequences(&mut input, &literals)
+    }
+
+    fn decode_literals(&mut self, input: &mut ForwardReader) -> Result<Vec<u8>> {
+        let byte0 = input.read_u8()? as usize;
+        let block_type = byte0 & 0x3;
+        let size_format = (byte0 >> 2) & 0x3;
+
+        if block_type < 2 {
+            let regenerated_size = match size_format {
+                0 | 2 => byte0 >> 3,
+                1 => (byte0 >> 4) + ((input.read_u8()? as usize) << 4),
+                _ => (byte0 >> 4) + ((input.read_le(2)? as usize) << 4),
+            };
+            if regenerated_size > MAX_BLOCK_SIZE {
+                return Err(corrupted());
+            }
+            return if block_type == 0 {
+                Ok(input.read_slice(regenerated_size)?.to_vec())
+            } else {
+                Ok(vec![input.read_u8()?; regenerated_size])
+            };
+        }
+
+        let (header_len, size_bits, nr_streams) = match size_format {
+            0 => (3, 10, 1),
+            1 => (3, 10, 4),
+            2 => (4, 14, 4),
+            _ => (5, 18, 4),
+        };
+        let header = byte0 as u64 | (input.read_le(header_len - 1)? << 8);
+        let size_mask = (1u64 << size_bits) - 1;
+        let regenerated_size = ((header >> 4) & size_mask) as usize;
+        let compressed_size = ((header >> (4 + size_bits)) & size_mask) as usize;
+        if regenerated_size > MAX_BLOCK_SIZE {
+            return Err(corrupted());
+        }
+
+        let mut data = input.read_slice(compressed_size)?;
+        if block_type == 2 {
+            let (table, len) = HuffmanTable::read(data)?;
+            self.huffman_table = Some(table);
+            data = &data[len..];
+        }
+        let table = self.huffman_table.as_ref().ok_or_else(corrupted)?;
+
+        let mut literals = Vec::with_capacity(regenerated_size);
+        if nr_streams == 1 {
+            table.decode_stream(data, regenerated_size, &mut literals)?;
+            return Ok(literals);
+        }
+
+        if data.len() < 6 {
+            return Err(corrupted());
+        }
+        let sizes = [
+            u16::from_le_bytes([data[0], data[1]]) as usize,
+            u16::from_le_bytes([data[2], data[3]]) as usize,
+            u16::from_le_bytes([data[4], data[5]]) as usize,
+        ];
+        let mut streams = &data[6..];
+        let stream_regenerated_size = regenerated_size.div_ceil(4);
+        for size in sizes {
+            if size > streams.len() {
+                return Err(corrupted());
+            }
+            let (stream, rest) = streams.split_at(size);
+            table.decode_stream(stream, stream_regenerated_size, &mut literals)?;
+            streams = rest;
+        }
+        let last_size = regenerated_size
+            .checked_sub(stream_regenerated_size * 3)
+            .ok_or_else(corrupted)?;
+        table.decode_stream(streams, last_size, &mut literals)?;
+        Ok(literals)
+    }
+
+    fn decode_sequences(&mut self, input: &mut ForwardReader, literals: &[u8]) -> Result<()> {
+        let byte0 = input.read_u8()? as usize;
+        let nr_sequences = match byte0 {
+            0 => 0,
+            1..=127 => byte0,
+            128..=254 => ((byte0 - 128) << 8) + input.read_u8()? as usize,
+            _ => input.read_le(2)? as usize + 0x7F00,
+        };
+        if nr_sequences == 0 {
+            self.reserve(literals.len())?;
+            self.output.extend_from_slice(literals);
+            return Ok(());
+        }
+
+        let modes = input.read_u8()?;
+        if modes & 0x3 != 0 {
+            return Err(corrupted());
+        }
+        update_fse_table(
+            &mut self.ll_table,
+            modes >> 6,
+            input,
+            &LL_DEFAULT_DISTRIBUTION,
+            LL_DEFAULT_ACCURACY_LOG,
+            MAX_LL_ACCURACY_LOG,
+        )?;
+        update_fse_table(
+            &mut self.of_table,
+            (modes >> 4) & 0x3,
+            input,
+            &OF_DEFAULT_DISTRIBUTION,
+            OF_DEFAULT_ACCURACY_LOG,
+            MAX_OF_ACCURACY_LOG,
+        )?;
+        update_fse_table(
+            &mut self.ml_table,
+            (modes >> 2) & 0x3,
+            input,
+            &ML_DEFAULT_DISTRIBUTION,
+            ML_DEFAULT_ACCURACY_LOG,
+            MAX_ML_ACCURACY_LOG,
+        )?;
+        let ll_table = self.ll_table.as_ref().unwrap();
+        let of_table = self.of_table.as_ref().unwrap();
+        let ml_table = self.ml_table.as_ref().unwrap();
+
+        let mut bits = BackwardReader::new(input.read_slice(input.remaining())?)?;
+        let mut ll_state = ll_table.init_state(&mut bits);
+        let mut of_state = of_table.init_state(&mut bits);
+        let mut ml_state = ml_table.init_state(&mut bits);
+
+        let mut literals_pos = 0;
+        for idx in 0..nr_sequences {
+            let of_code = of_table.symbol(of_state);
+            let ll_code = ll_table.symbol(ll_state) as usize;
+            let ml_code = ml_table.symbol(ml_state) as usize;
+            if of_code > MAX_OF_CODE || ll_code >= LL_CODES.len() || ml_code >= ML_CODES.len() {
+                return Err(corrupted());
+            }
+
+            let offset_value = (1usize << of_code) + bits.read(of_code) as usize;
+            let (ml_base, ml_bits) = ML_CODES[ml_code];
+            let match_len = ml_base as usize + bits.read(ml_bits) as usize;
+            let (ll_base, ll_bits) = LL_CODES[ll_code];
+            let literals_len = ll_base as usize + bits.read(ll_bits) as usize;
+
+            if idx + 1 < nr_sequences {
+                ll_state = ll_table.update_state(ll_state, &mut bits);
+                ml_state = ml_table.update_state(ml_state, &mut bits);
+                of_state = of_table.update_state(of_state, &mut bits);
+            }
+
+            let offset = resolve_offset(&mut self.repeat_offsets, offset_value, literals_len)?;
+
+            let literals_end = literals_pos + literals_len;
+            if literals_end > literals.len() {
+                return Err(corrupted());
+            }
+            self.reserve(literals_len + match_len)?;
+            self.output
+                .extend_from_slice(&literals[literals_pos..literals_end]);
+            literals_pos = literals_end;
+
+            if offset > self.output.len() - self.frame_start {
+                return Err(corrupted());
+            }
+            let match_start = self.output.len() - offset;
+            for pos in match_start..match_start + match_len {
+                let byte = self.output[pos];
+                self.output.push(byte);
+            }
+        }
+        if bits.remaining() != 0 {
+            return Err(corrupted());
+        }
+
+        let rest = &literals[literals_pos..];
+        self.reserve(rest.len())?;
+        self.output.extend_from_slice(rest);
+        Ok(())
+    }
+}
+
+/// Resolves an offset value, which may refer to a repeated offset.
+fn resolve_offset(
+    repeat_offsets: &mut [usize; 3],
+    offset_value: usize,
+    literals_len: usize,
+) -> Result<usize> {
+    let [rep0, rep1, rep2] = *repeat_offsets;
+    if offset_value > 3 {
+        let offset = offset_value - 3;
+        *repeat_offsets = [offset, rep0, rep1];
+        return Ok(offset);
+    }
+
+    // The repeated offsets are shifted by one if there are no literals.
+    let idx = offset_value - 1 + (literals_len == 0) as usize;
+    let offset = match idx {
+        0 => return Ok(rep0),
+        1 => rep1,
+        2 => rep2,
+        _ => rep0 - 1,
+    };
+    if offset == 0 {
+        return Err(corrupted());
+    }
+    *repeat_offsets = if idx == 1 {
+        [offset, rep0, rep2]
+    } else {
+        [offset, rep0, rep1]
+    };
+    Ok(offset)
+}
+
+/// Updates the FSE table of a kind of codes according to its compression mode.
+fn update_fse_table(
+    table: &mut Option<FseTable>,
+    mode: u8,
+    input: &mut ForwardReader,
+    default_distribution: &[i16],
+    default_accuracy_log: u8,
+    max_accuracy_log: u8,
+) -> Result<()> {
+    match mode {
+        0 => *table = Some(FseTable::new(default_distribution, default_accuracy_log)?),
+        1 => *table = Some(FseTable::new_rle(input.read_u8()?)),
+        2 => {
+            let (distribution, accuracy_log, len) =
+                read_distribution(input.rest(), max_accuracy_log)?;
+            input.read_slice(len)?;
+            *table = Some(FseTable::new(&distribution, accuracy_log)?);
+        }
+        _ => {
+            if table.is_none() {
+                return Err(corrupted());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads an FSE table description, returning the distribution, the accuracy log and
+/// the number of bytes read.
+fn read_distribution(data: &[u8], max_accuracy_log: u8) -> Result<(Vec<i16>, u8, usize)> {
+    let mut bits = LittleEndianBits::new(data);
+    let accuracy_log = bits.read(4)? as u8 + 5;
+    if accuracy_log > max_accuracy_log {
+        return Err(corrupted());
+    }
+
+    let mut distribution = Vec::new();
+    let mut remaining = (1i32 << accuracy_log) + 1;
+    let mut threshold = 1i32 << accuracy_log;
+    let mut nr_bits = accuracy_log + 1;
+    while remaining > 1 {
+        let max = 2 * threshold - 1 - remaining;
+        let value = bits.peek(nr_bits)? as i32;
+        let mut count = if (value & (threshold - 1)) < max {
+            bits.skip(nr_bits - 1);
+            value & (threshold - 1)
+        } else {
+            bits.skip(nr_bits);
+            let value = value & (2 * threshold - 1);
+            if value >= threshold {
+                value - max
+            } else {
+                value
+            }
+        };
+        count -= 1;
+        remaining -= count.abs();
+        distribution.push(count as i16);
+
+        if count == 0 {
+            loop {
+                let repeat = bits.read(2)?;
+                distribution.resize(distribution.len() + repeat as usize, 0);
+                if repeat != 3 {
+                    break;
+                }
+            }
+        }
+        while remaining < threshold && nr_bits > 1 {
+            nr_bits -= 1;
+            threshold >>= 1;
+        }
+        if distribution.len() > 256 {
+            return Err(corrupted());
+        }
+    }
+    if remaining != 1 {
+        return Err(corrupted());
+    }
+
+    let len = bits.consumed_bytes();
+    if len > data.len() {
+        return Err(corrupted());
+    }
+    Ok((distribution, accuracy_log, len))
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct FseEntry {
+    symbol: u8,
+    nr_bits: u8,
+    baseline: u16,
+}
+
+/// A decoding table of finite state entropy (FSE).
+#[derive(Debug)]
+struct FseTable {
+    entries: Vec<FseEntry>,
+    accuracy_log: u8,
+}
+
+impl FseTable {
+    fn new(distribution: &[i16], accuracy_log: u8) -> Result<Self> {
+        let size = 1usize << accuracy_log;
+        let mut entries = vec![FseEntry::default(); size];
+        let mut next_states = vec![0u16; distribution.len()];
+
+        // The symbols with the "less than one" probability take the highest states.
+        let mut high_threshold = size;
+        for (symbol, &prob) in distribution.iter().enumerate() {
+            if prob == -1 {
+                high_threshold = high_threshold.checked_sub(1).ok_or_else(corrupted)?;
+                entries[high_threshold].symbol = symbol as u8;
+                next_states[symbol] = 1;
+            } else {
+                next_states[symbol] = prob.max(0) as u16;
+            }
+        }
+
+        let step = (size >> 1) + (size >> 3) + 3;
+        let mask = size - 1;
+        let mut pos = 0;
+        for (symbol, &prob) in distribution.iter().enumerate() {
+            for _ in 0..prob.max(0) {
+                entries[pos].symbol = symbol as u8;
+                pos = (pos + step) & mask;
+                while pos >= high_threshold {
+                    pos = (pos + step) & mask;
+                }
+            }
+        }
+        if pos != 0 {
+            return Err(corrupted());
+        }
+
+        for entry in entries.iter_mut() {
+            let next_state = &mut next_states[entry.symbol as usize];
+            let state = *next_state;
+            *next_state += 1;
+            let nr_bits = accuracy_log - (15 - state.leading_zeros() as u8);
+            entry.nr_bits = nr_bits;
+            entry.baseline = ((state as usize) << nr_bits).wrapping_sub(size) as u16;
+        }
+
+        Ok(Self {
+            entries,
+            accuracy_log,
+        })
+    }
+
+    fn new_rle(symbol: u8) -> Self {
+        Self {
+            entries: vec![FseEntry {
+                symbol,
+                nr_bits: 0,
+                baseline: 0,
+            }],
+            accuracy_log: 0,
+        }
+    }
+
+    fn init_state(&self, bits: &mut BackwardReader) -> usize {
+        bits.read(self.accuracy_log) as usize
+    }
+
+    fn symbol(&self, state: usize) -> u8 {
+        self.entries[state].symbol
+    }
+
+    fn update_state(&self, state: usize, bits: &mut BackwardReader) -> usize {
+        let entry = &self.entries[state];
+        entry.baseline as usize + bits.read(entry.nr_bits) as usize
+    }
+}
+
+/// A decoding table of the Huffman-coded literals.
+#[derive(Debug)]
+struct HuffmanTable {
+    /// The symbols and the code lengths, indexed by the next `max_bits` bits.
+    entries: Vec<(u8, u8)>,
+    max_bits: u8,
+}
+
+impl HuffmanTable {
+    /// Reads a Huffman tree description, returning the table and the number of bytes read.
+    fn read(data: &[u8]) -> Result<(Self, usize)> {
+        let header = *data.first().ok_or_else(corrupted)? as usize;
+        let (mut weights, len) = if header < 128 {
+            let compressed = data.get(1..1 + header).ok_or_else(corrupted)?;
+            (read_fse_weights(compressed)?, 1 + header)
+        } else {
+            let nr_weights = header - 127;
+            let len = 1 + nr_weights.div_ceil(2);
+            let packed = data.get(1..len).ok_or_else(corrupted)?;
+            let weights = (0..nr_weights)
+                .map(|idx| {
+                    let byte = packed[idx / 2];
+                    if idx % 2 == 0 { byte >> 4 } else { byte & 0xF }
+                })
+                .collect();
+            (weights, len)
+        };
+
+        // The weight of the last symbol is implied by the others.
+        let mut total = 0u32;
+        for &weight in weights.iter() {
+            if weight > MAX_HUFFMAN_BITS {
+                return Err(corrupted());
+            }
+            if weight > 0 {
+                total += 1 << (weight - 1);
+            }
+        }
+        if total == 0 || weights.len() > 255 {
+            return Err(corrupted());
+        }
+        let max_bits = (32 - total.leading_zeros()) as u8;
+        let rest = (1u32 << max_bits) - total;
+        if !rest.is_power_of_two() || max_bits > MAX_HUFFMAN_BITS {
+            return Err(corrupted());
+        }
+        weights.push(rest.trailing_zeros() as u8 + 1);
+
+        let mut entries = vec![(0u8, 0u8); 1 << max_bits];
+        let mut pos = 0;
+        for weight in 1..=max_bits {
+            for (symbol, _) in weights.iter().enumerate().filter(|(_, w)| **w == weight) {
+                let nr_entries = 1usize << (weight - 1);
+                entries[pos..pos + nr_entries].fill((symbol as u8, max_bits + 1 - weight));
+                pos += nr_entries;
+            }
+        }
+
+        Ok((Self { entries, max_bits }, len))
+    }
+
+    /// Decodes a stream of `len` literals.
+    fn decode_stream(&self, data: &[u8], len: usize, literals: &mut Vec<u8>) -> Result<()> {
+        let mut bits = BackwardReader::new(data)?;
+        for _ in 0..len {
+            let (symbol, nr_bits) = self.entries[bits.peek(self.max_bits) as usize];
+            bits.skip(nr_bits);
+            literals.push(symbol);
+        }
+        if bits.remaining() != 0 {
+            return Err(corrupted());
+        }
+        Ok(())
+    }
+}
+
+/// Reads the FSE-compressed weights of a Huffman tree description.
+fn read_fse_weights(data: &[u8]) -> Result<Vec<u8>> {
+    let (distribution, accuracy_log, len) =
+        read_distribution(data, MAX_HUFFMAN_WEIGHT_ACCURACY_LOG)?;
+    let table = FseTable::new(&distribution, accuracy_log)?;
+    let mut bits = BackwardReader::new(&data[len..])?;
+
+    // Two interleaved states share the bitstream, and decoding stops at its end.
+    let mut states = [table.init_state(&mut bits), table.init_state(&mut bits)];
+    let mut weights = Vec::new();
+    let mut idx = 0;
+    loop {
+        weights.push(table.symbol(states[idx]));
+        states[idx] = table.update_state(states[idx], &mut bits);
+        if bits.is_overflowed() {
+            weights.push(table.symbol(states[1 - idx]));
+            break;
+        }
+        if weights.len() > 255 {
+            return Err(corrupted());
+        }
+        idx = 1 - idx;
+    }
+    Ok(weights)
+}
+
+/// A reader of the bytes in the forward direction.
+struct ForwardReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> ForwardReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len()
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        self.data
+    }
+
+    fn read_slice(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.data.len() {
+            return Err(corrupted());
+        }
+        let (slice, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(slice)
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_slice(1)?[0])
+    }
+
+    fn read_u32(&mut self) -> Result<u32> {
+        Ok(self.read_le(4)? as u32)
+    }
+
+    /// Reads a little-endian integer of `len` bytes.
+    fn read_le(&mut self, len: usize) -> Result<u64> {
+        let bytes = self.read_slice(len)?;
+        Ok(bytes
+            .iter()
+            .rev()
+            .fold(0, |value, &byte| (value << 8) | byte as u64))
+    }
+}
+
+/// A reader of the bits in the forward direction, starting from the lowest bit.
+struct LittleEndianBits<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> LittleEndianBits<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn peek(&self, nr_bits: u8) -> Result<u64> {
+        let mut value = 0;
+        for idx in 0..nr_bits as usize {
+            let pos = self.pos + idx;
+            let byte = self.data.get(pos / 8).copied().unwrap_or(0);
+            value |= (((byte >> (pos % 8)) & 1) as u64) << idx;
+        }
+        Ok(value)
+    }
+
+    fn skip(&mut self, nr_bits: u8) {
+        self.pos += nr_bits as usize;
+    }
+
+    fn read(&mut self, nr_bits: u8) -> Result<u64> {
+        let value = self.peek(nr_bits)?;
+        self.skip(nr_bits);
+        Ok(value)
+    }
+
+    fn consumed_bytes(&self) -> usize {
+        self.pos.div_ceil(8)
+    }
+}
+
+/// A reader of the bits in the backward direction, starting from the highest bit.
+///
+/// The stream ends with a padding of zeros and a one bit. The bits beyond the start of
+/// the stream are read as zeros.
+struct BackwardReader<'a> {
+    data: &'a [u8],
+    /// The number of unread bits.
+    pos: isize,
+}
+
+impl<'a> BackwardReader<'a> {
+    fn new(data: &'a [u8]) -> Result<Self> {
+        let last = *data.last().ok_or_else(corrupted)?;
+        if last == 0 {
+            return Err(corrupted());
+        }
+        let pos = (data.len() * 8) as isize - last.leading_zeros() as isize - 1;
+        Ok(Self { data, pos })
+    }
+
+    fn peek(&self, nr_bits: u8) -> u64 {
+        let mut value = 0;
+        for idx in 0..nr_bits as isize {
+            let pos = self.pos - idx - 1;
+            let bit = if pos < 0 {
+                0
+            } else {
+                (self.data[pos as usize / 8] >> (pos % 8)) & 1
+            };
+            value = (value << 1) | bit as u64;
+        }
+        value
+    }
+
+    fn skip(&mut self, nr_bits: u8) {
+        self.pos -= nr_bits as isize;
+    }
+
+    fn read(&mut self, nr_bits: u8) -> u64 {
+        let value = self.peek(nr_bits);
+        self.skip(nr_bits);
+        value
+    }
+
+    fn remaining(&self) -> isize {
+        self.pos
+    }
+
+    fn is_overflowed(&self) -> bool {
+        self.pos < 0
+    }
+}
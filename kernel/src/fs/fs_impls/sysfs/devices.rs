@@ -0,0 +1,358 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The device model in sysfs.
+//!
+//! Each device is represented by a kernel object at `/sys/devices/virtual/<class>/<name>`,
+//! which has the `dev` and `uevent` attributes and a `subsystem` symlink to its class.
+//! The device can also be found via the following symlinks:
+//!  - `/sys/class/<class>/<name>`;
+//!  - `/sys/dev/{char,block}/<major>:<minor>`, if the device has a device number;
+//!  - `/sys/block/<name>`, if the device is a disk.
+//!
+//! When a device is added or removed, an `add` or `remove` uevent is sent to the user space via
+//! netlink. Writing to the `uevent` attribute of a device triggers a synthetic uevent.
+//!
+//! Reference: <https://docs.kernel.org/admin-guide/sysfs-rules.html>
+
+use device_id::DeviceId;
+use ostd::{cpu::CpuId, util::id_set::Id};
+use spin::Once;
+
+use super::kobject::{KObject, KObjectLink, ShowFn, StoreFn};
+use crate::{
+    cpu::{
+        freq,
+        hotplug::{self, is_cpu_online},
+    },
+    device::DeviceType,
+    net::socket::netlink::{self, SysObjAction},
+    prelude::*,
+};
+
+/// A device to be added to the device model in sysfs.
+pub struct SysDevice {
+    /// The class of the device (e.g., `tty`, `block`, or `net`).
+    pub class: &'static str,
+    /// The name of the device.
+    pub name: String,
+    /// The type and the ID of the device, if the device has a device number.
+    pub dev: Option<(DeviceType, DeviceId)>,
+    /// The additional `KEY=VALUE` pairs in the `uevent` attribute.
+    pub uevent_vars: Vec<(&'static str, String)>,
+    /// The additional read-only attributes.
+    pub attrs: Vec<(&'static str, ShowFn)>,
+}
+
+struct DeviceModel {
+    /// The `/sys/devices/virtual` directory.
+    virtual_devices: Arc<KObject>,
+    /// The `/sys/class` directory.
+    classes: Arc<KObject>,
+    /// The `/sys/block` directory.
+    block: Arc<KObject>,
+    /// The `/sys/dev/char` directory.
+    dev_char: Arc<KObject>,
+    /// The `/sys/dev/block` directory.
+    dev_block: Arc<KObject>,
+    /// The directories of each class in `/sys/devices/virtual` and `/sys/class`.
+    class_dirs: Mutex<BTreeMap<&'static str, (Arc<KObject>, Arc<KObject>)>>,
+}
+
+static DEVICE_MODEL: Once<DeviceModel> = Once::new();
+
+pub(super) fn init() {
+    let root = super::systree_singleton().root();
+
+    let devices = KObject::new_dir("devices");
+    let system = KObject::new_dir("system");
+    system.add_child(new_cpu_kobject()).unwrap();
+    devices.add_child(system).unwrap();
+    let virtual_devices = KObject::new_dir("virtual");
+    devices.add_child(virtual_devices.clone()).unwrap();
+    root.add_child(devices).unwrap();
+
+    let classes = KObject::new_dir("class");
+    root.add_child(classes.clone()).unwrap();
+
+    let block = KObject::new_dir("block");
+    root.add_child(block.clone()).unwrap();
+
+    let dev = KObject::new_dir("dev");
+    let dev_char = KObject::new_dir("char");
+    dev.add_child(dev_char.clone()).unwrap();
+    let dev_block = KObject::new_dir("block");
+    dev.add_child(dev_block.clone()).unwrap();
+    root.add_child(dev).unwrap();
+
+    DEVICE_MODEL.call_once(|| DeviceModel {
+        virtual_devices,
+        classes,
+        block,
+        dev_char,
+        dev_block,
+        class_dirs: Mutex::new(BTreeMap::new()),
+    });
+}
+
+/// Creates the kernel object of the `/sys/devices/system/cpu` directory.
+///
+/// Reference: <https://docs.kernel.org/admin-guide/cputopology.html>
+fn new_cpu_kobject() -> Arc<KObject> {
+    // All CPUs are possible and present since the CPUs cannot be physically added or removed.
+    let show_cpus = || format_cpu_list(ostd::cpu::all_cpus());
+    let show_online = || format_cpu_list(ostd::cpu::all_cpus().filter(|cpu| is_cpu_online(*cpu)));
+    let show_offline = || format_cpu_list(ostd::cpu::all_cpus().filter(|cpu| !is_cpu_online(*cpu)));
+
+    let cpu = KObject::new(
+        "cpu",
+        vec![
+            ("possible", Box::new(show_cpus) as ShowFn),
+            ("present", Box::new(show_cpus) as ShowFn),
+            ("online", Box::new(show_online) as ShowFn),
+            ("offline", Box::new(show_offline) as ShowFn),
+        ],
+    );
+    let cpufreq = freq::new_cpufreq_kobject();
+    let has_cpufreq = cpufreq.is_some();
+    if let Some(cpufreq) = cpufreq {
+        cpu.add_child(cpufreq).unwrap();
+    }
+
+    for cpu_id in ostd::cpu::all_cpus() {
+        let online = Box::new(move || format!("{}\n", u8::from(is_cpu_online(cpu_id)))) as ShowFn;
+
+        let name = format!("cpu{}", cpu_id.as_usize());
+        let cpu_kobject = if hotplug::is_cpu_hotpluggable(cpu_id) {
+            let store_online = Box::new(move |value: &str| {
+                let res = match value.trim() {
+                    "0" => hotplug::cpu_down(cpu_id),
+                    "1" => hotplug::cpu_up(cpu_id),
+                    _ => return Err(aster_systree::Error::InvalidOperation),
+                };
+                res.map_err(|err| match err.error() {
+                    Errno::EBUSY => aster_systree::Error::ResourceUnavailable,
+                    _ => aster_systree::Error::InvalidOperation,
+                })
+            }) as StoreFn;
+            KObject::new_with_stores(
+                name,
+                vec![("online", online)],
+                vec![("online", store_online)],
+            )
+        } else {
+            KObject::new(name, vec![("online", online)])
+        };
+        if has_cpufreq {
+            cpu_kobject
+                .add_child(KObjectLink::new(
+                    "cpufreq",
+                    format!("../cpufreq/policy{}", cpu_id.as_usize()),
+                ))
+                .unwrap();
+        }
+        cpu.add_child(cpu_kobject).unwrap();
+    }
+
+    cpu
+}
+
+/// Formats the CPUs as a list of ranges, e.g., `0-3,5`.
+fn format_cpu_list(cpus: impl Iterator<Item = CpuId>) -> String {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for cpu in cpus.map(|cpu| cpu.as_usize()) {
+        match ranges.last_mut() {
+            Some((_, last)) if *last + 1 == cpu => *last = cpu,
+            _ => ranges.push((cpu, cpu)),
+        }
+    }
+
+    let list = ranges
+        .iter()
+        .map(|(first, last)| {
+            if first == last {
+                format!("{}", first)
+            } else {
+                format!("{}-{}", first, last)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    format!("{}\n", list)
+}
+
+/// Adds a device to the device model in sysfs, returning its kernel object.
+///
+/// If `parent` is specified, the device is added as a child of the parent device
+/// (e.g., a partition of a disk). Otherwise, it is added to `/sys/devices/virtual/<class>`.
+pub fn add_device(device: SysDevice, parent: Option<&Arc<KObject>>) -> Result<Arc<KObject>> {
+    let SysDevice {
+        class,
+        name,
+        dev,
+        uevent_vars,
+        mut attrs,
+    } = device;
+
+    let model = DEVICE_MODEL.get().unwrap();
+    let (virtual_class_dir, class_dir) = model
+        .class_dirs
+        .lock()
+        .entry(class)
+        .or_insert_with(|| {
+            let virtual_class_dir = KObject::new_dir(class);
+            model
+                .virtual_devices
+                .add_child(virtual_class_dir.clone())
+                .unwrap();
+            let class_dir = KObject::new_dir(class);
+            model.classes.add_child(class_dir.clone()).unwrap();
+            (virtual_class_dir, class_dir)
+        })
+        .clone();
+
+    let mut envs = Vec::new();
+    if let Some((_, id)) = dev.as_ref() {
+        let dev_str = format!("{}:{}\n", id.major().get(), id.minor().get());
+        attrs.push(("dev", Box::new(move || dev_str.clone())));
+
+        envs.push((String::from("MAJOR"), id.major().get().to_string()));
+        envs.push((String::from("MINOR"), id.minor().get().to_string()));
+    }
+    envs.extend(
+        uevent_vars
+            .into_iter()
+            .map(|(key, value)| (String::from(key), value)),
+    );
+
+    let parent_dir = parent.unwrap_or(&virtual_class_dir);
+    let device_path = format!("{}/{}", parent_dir.sysfs_path(), name);
+
+    let uevent: String = envs
+        .iter()
+        .map(|(key, value)| format!("{}={}\n", key, value))
+        .collect();
+    attrs.push(("uevent", Box::new(move || uevent.clone())));
+    let stores = vec![(
+        "uevent",
+        new_uevent_store(device_path.clone(), class, envs.clone()),
+    )];
+
+    let kobject = KObject::new_with_stores(name.clone(), attrs, stores);
+    parent_dir.add_child(kobject.clone())?;
+
+    let to_root = "../".repeat(device_path.split('/').count());
+    kobject.add_child(KObjectLink::new(
+        "subsystem",
+        format!("{}class/{}", to_root, class),
+    ))?;
+
+    class_dir.add_child(KObjectLink::new(
+        name.clone(),
+        format!("../../{}", device_path),
+    ))?;
+
+    if let Some((type_, id)) = dev.as_ref() {
+        let dev_dir = match type_ {
+            DeviceType::Char => &model.dev_char,
+            DeviceType::Block => &model.dev_block,
+        };
+        dev_dir.add_child(KObjectLink::new(
+            format!("{}:{}", id.major().get(), id.minor().get()),
+            format!("../../{}", device_path),
+        ))?;
+    }
+
+    if class == "block" && parent.is_none() {
+        model
+            .block
+            .add_child(KObjectLink::new(name, format!("../{}", device_path)))?;
+    }
+
+    send_uevent(SysObjAction::Add, &device_path, class, envs);
+
+    Ok(kobject)
+}
+
+/// Removes a device from the device model in sysfs.
+///
+/// `parent` should be the same as the one specified when the device was added.
+pub fn remove_device(
+    class: &str,
+    name: &str,
+    dev: Option<(DeviceType, DeviceId)>,
+    parent: Option<&Arc<KObject>>,
+) -> Result<()> {
+    let model = DEVICE_MODEL.get().unwrap();
+    let Some((virtual_class_dir, class_dir)) = model.class_dirs.lock().get(class).cloned() else {
+        return_errno_with_message!(Errno::ENOENT, "the device class does not exist");
+    };
+
+    if let Some((type_, id)) = dev {
+        let dev_dir = match type_ {
+            DeviceType::Char => &model.dev_char,
+            DeviceType::Block => &model.dev_block,
+        };
+        dev_dir.remove_child(&format!("{}:{}", id.major().get(), id.minor().get()))?;
+    }
+
+    if class == "block" && parent.is_none() {
+        model.block.remove_child(name)?;
+    }
+
+    class_dir.remove_child(name)?;
+
+    let parent_dir = parent.unwrap_or(&virtual_class_dir);
+    let device_path = format!("{}/{}", parent_dir.sysfs_path(), name);
+    let kobject = parent_dir.remove_child(name)?;
+
+    // The `remove` uevent carries the same variables as the `add` uevent.
+    let envs = kobject
+        .cast_to_node()
+        .and_then(|node| node.show_attr("uevent").ok())
+        .map(|uevent| parse_uevent_envs(&uevent))
+        .unwrap_or_default();
+    send_uevent(SysObjAction::Remove, &device_path, class, envs);
+
+    Ok(())
+}
+
+/// Creates the [`StoreFn`] of the `uevent` attribute of a device.
+///
+/// Writing to the `uevent` attribute triggers a synthetic uevent.
+fn new_uevent_store(
+    device_path: String,
+    class: &'static str,
+    envs: Vec<(String, String)>,
+) -> StoreFn {
+    Box::new(move |value| {
+        netlink::broadcast_synthetic_uevent(
+            value,
+            format!("/{}", device_path),
+            String::from(class),
+            envs.clone(),
+        )
+        .map_err(|_| aster_systree::Error::InvalidOperation)
+    })
+}
+
+/// Sends a uevent of a device to the user space.
+fn send_uevent(action: SysObjAction, device_path: &str, class: &str, envs: Vec<(String, String)>) {
+    // Like Linux, failing to send the uevent does not fail the operation on the device.
+    if let Err(err) = netlink::broadcast_uevent(
+        action,
+        format!("/{}", device_path),
+        String::from(class),
+        envs,
+    ) {
+        warn!("failed to send the uevent of '{}': {:?}", device_path, err);
+    }
+}
+
+/// Parses the `KEY=VALUE` pairs in the content of a `uevent` attribute.
+fn parse_uevent_envs(uevent: &str) -> Vec<(String, String)> {
+    uevent
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (String::from(key), String::from(value)))
+        .collect()
+}
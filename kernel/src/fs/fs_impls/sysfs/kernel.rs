@@ -6,10 +6,13 @@ use aster_systree::{
     BranchNodeFields, Error, Result, SysAttrSetBuilder, SysBranchNode, SysNode, SysPerms, SysStr,
     inherit_sys_branch_node,
 };
+use aster_util::printer::VmPrinter;
 use inherit_methods_macro::inherit_methods;
 use ostd::mm::{VmReader, VmWriter};
 use spin::Once;
 
+use crate::{power::kexec::is_image_loaded, thread::oops::oops_count};
+
 /// Registers a new kernel `SysNode`.
 pub(super) fn register(config_obj: Arc<dyn SysNode>) -> crate::prelude::Result<()> {
     KERNEL_SYS_NODE_ROOT.get().unwrap().add_child(config_obj)?;
@@ -52,8 +55,21 @@ impl KernelSysNodeRoot {
     /// Creates a new `KernelSysNodeRoot` instance.
     fn new() -> Arc<Self> {
         let name = SysStr::from("kernel");
-        let builder = SysAttrSetBuilder::new();
+        let mut builder = SysAttrSetBuilder::new();
         // TODO: Add more kernel-specific attributes.
+        builder.add(
+            SysStr::from("kexec_loaded"),
+            SysPerms::DEFAULT_RO_ATTR_PERMS,
+        );
+        builder.add(
+            SysStr::from("kexec_crash_loaded"),
+            SysPerms::DEFAULT_RO_ATTR_PERMS,
+        );
+        builder.add(
+            SysStr::from("kexec_crash_size"),
+            SysPerms::DEFAULT_RO_ATTR_PERMS,
+        );
+        builder.add(SysStr::from("oops_count"), SysPerms::DEFAULT_RO_ATTR_PERMS);
         let attrs = builder
             .build()
             .expect("Failed to build kernel attribute set");
@@ -68,9 +84,24 @@ impl KernelSysNodeRoot {
 }
 
 inherit_sys_branch_node!(KernelSysNodeRoot, fields, {
-    fn read_attr(&self, _name: &str, _writer: &mut VmWriter) -> Result<usize> {
-        // TODO: Add support for reading attributes.
-        Err(Error::AttributeError)
+    fn read_attr_at(&self, name: &str, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        match name {
+            "kexec_loaded" => writeln!(printer, "{}", is_image_loaded() as u8)?,
+            "kexec_crash_loaded" => {
+                writeln!(printer, "{}", ostd::power::is_crash_image_loaded() as u8)?
+            }
+            "kexec_crash_size" => writeln!(
+                printer,
+                "{}",
+                ostd::power::crash_kernel_region().map_or(0, |region| region.len())
+            )?,
+            "oops_count" => writeln!(printer, "{}", oops_count())?,
+            _ => return Err(Error::AttributeError),
+        }
+
+        Ok(printer.bytes_written())
     }
 
     fn write_attr(&self, _name: &str, _reader: &mut VmReader) -> Result<usize> {
@@ -0,0 +1,142 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{boxed::Box, string::String, sync::Arc, vec::Vec};
+use core::fmt::Debug;
+
+use aster_systree::{
+    BranchNodeFields, Error, MAX_ATTR_SIZE, Result, SymlinkNodeFields, SysAttrSetBuilder, SysObj,
+    SysPerms, SysStr, inherit_sys_branch_node, inherit_sys_symlink_node,
+};
+use aster_util::printer::VmPrinter;
+use inherit_methods_macro::inherit_methods;
+use ostd::mm::{VmReader, VmWriter};
+
+/// A function that shows the value of an attribute.
+pub type ShowFn = Box<dyn Fn() -> String + Send + Sync>;
+
+/// A function that stores a new value to an attribute.
+pub type StoreFn = Box<dyn Fn(&str) -> Result<()> + Send + Sync>;
+
+/// A kernel object in sysfs.
+///
+/// A kernel object is a directory in sysfs whose files are attributes. The value of each
+/// attribute is generated by its [`ShowFn`] when the attribute is read. An attribute is writable
+/// only if it also has a [`StoreFn`]. Kernel objects form the device model in sysfs (e.g.,
+/// `/sys/devices`, `/sys/class`, and `/sys/block`).
+pub struct KObject {
+    fields: BranchNodeFields<dyn SysObj, Self>,
+    attrs: Vec<(&'static str, ShowFn)>,
+    stores: Vec<(&'static str, StoreFn)>,
+}
+
+#[inherit_methods(from = "self.fields")]
+impl KObject {
+    /// Creates a kernel object without any attributes.
+    pub fn new_dir(name: impl Into<SysStr>) -> Arc<Self> {
+        Self::new(name, Vec::new())
+    }
+
+    /// Creates a kernel object with the read-only attributes.
+    pub fn new(name: impl Into<SysStr>, attrs: Vec<(&'static str, ShowFn)>) -> Arc<Self> {
+        Self::new_with_stores(name, attrs, Vec::new())
+    }
+
+    /// Creates a kernel object with the attributes, some of which are writable.
+    ///
+    /// Each attribute in `stores` should also have a [`ShowFn`] in `attrs`.
+    pub fn new_with_stores(
+        name: impl Into<SysStr>,
+        attrs: Vec<(&'static str, ShowFn)>,
+        stores: Vec<(&'static str, StoreFn)>,
+    ) -> Arc<Self> {
+        let mut builder = SysAttrSetBuilder::new();
+        for (attr_name, _) in stores.iter() {
+            builder.add(SysStr::from(*attr_name), SysPerms::DEFAULT_RW_ATTR_PERMS);
+        }
+        for (attr_name, _) in attrs.iter() {
+            builder.add(SysStr::from(*attr_name), SysPerms::DEFAULT_RO_ATTR_PERMS);
+        }
+        let attr_set = builder
+            .build()
+            .expect("Failed to build the attribute set of a kernel object");
+
+        Arc::new_cyclic(|weak_self| {
+            let fields = BranchNodeFields::new(name.into(), attr_set, weak_self.clone());
+            KObject {
+                fields,
+                attrs,
+                stores,
+            }
+        })
+    }
+
+    /// Adds a child to this kernel object.
+    pub fn add_child(&self, new_child: Arc<dyn SysObj>) -> Result<()>;
+
+    /// Removes a child from this kernel object.
+    pub fn remove_child(&self, child_name: &str) -> Result<Arc<dyn SysObj>>;
+
+    /// Returns the path of this kernel object relative to the root of sysfs.
+    ///
+    /// The returned path does not start with `/`.
+    pub fn sysfs_path(&self) -> String {
+        String::from(self.path().trim_start_matches('/'))
+    }
+}
+
+impl Debug for KObject {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("KObject")
+            .field("name", self.fields.name())
+            .finish_non_exhaustive()
+    }
+}
+
+inherit_sys_branch_node!(KObject, fields, {
+    fn read_attr_at(&self, name: &str, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let Some((_, show)) = self.attrs.iter().find(|(attr_name, _)| *attr_name == name) else {
+            return Err(Error::AttributeError);
+        };
+
+        let mut printer = VmPrinter::new_skip(writer, offset);
+        write!(printer, "{}", show())?;
+
+        Ok(printer.bytes_written())
+    }
+
+    fn write_attr(&self, name: &str, reader: &mut VmReader) -> Result<usize> {
+        let Some((_, store)) = self.stores.iter().find(|(attr_name, _)| *attr_name == name) else {
+            return Err(Error::AttributeError);
+        };
+
+        let (value, len) = reader
+            .read_cstring_until_end(MAX_ATTR_SIZE)
+            .map_err(|_| Error::PageFault)?;
+        let value = value.to_str().map_err(|_| Error::InvalidOperation)?;
+        store(value)?;
+
+        Ok(len)
+    }
+
+    fn perms(&self) -> SysPerms {
+        SysPerms::DEFAULT_RW_PERMS
+    }
+});
+
+/// A symlink between kernel objects in sysfs.
+#[derive(Debug)]
+pub struct KObjectLink {
+    fields: SymlinkNodeFields<Self>,
+}
+
+impl KObjectLink {
+    /// Creates a symlink whose target is relative to the directory containing the symlink.
+    pub fn new(name: impl Into<SysStr>, target: String) -> Arc<Self> {
+        Arc::new_cyclic(|weak_self| {
+            let fields = SymlinkNodeFields::new(name.into(), target, weak_self.clone());
+            KObjectLink { fields }
+        })
+    }
+}
+
+inherit_sys_symlink_node!(KObjectLink, fields);
@@ -1,14 +1,18 @@
 // SPDX-License-Identifier: MPL-2.0
 
+mod devices;
 mod fs;
 mod inode;
 mod kernel;
+mod kobject;
 #[cfg(ktest)]
 mod test;
 
 use aster_systree::SysNode;
 pub use aster_systree::primary_tree as systree_singleton;
+pub use devices::{SysDevice, add_device, remove_device};
 use fs::SysFsType;
+pub use kobject::{KObject, ShowFn, StoreFn};
 
 use crate::{fs::vfs::registry, prelude::*};
 
@@ -18,6 +22,7 @@ pub fn init() {
     registry::register(&SysFsType).unwrap();
 
     kernel::init();
+    devices::init();
 }
 
 /// Registers a new kernel `SysNode`.
@@ -1,7 +1,9 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use super::TMPFS_MAGIC;
 use crate::{
     fs::{
+        file::InodeMode,
         ramfs::RamFs,
         vfs::{
             file_system::{FileSystem, FsEventSubscriberStats, FsFlags, SuperBlock},
@@ -10,23 +12,53 @@ use crate::{
         },
     },
     prelude::*,
+    process::{Gid, Uid},
 };
 
 /// The temporary file system (tmpfs) structure.
+///
+/// The blocks and inodes that a tmpfs can use are limited by the `size`,
+/// `nr_blocks`, and `nr_inodes` mount options, which can be changed by remounting.
 //
 // TODO: Currently, tmpfs is implemented as a thin wrapper around ramfs.
-// In the future we need to implement tmpfs-specific features such as
-// memory limits and swap support.
+// In the future we need to support swapping out the pages of tmpfs,
+// which requires the kernel to support swap first.
 pub struct TmpFs {
     inner: Arc<RamFs>,
 }
 
 impl TmpFs {
+    /// Creates a tmpfs without any limits.
+    ///
+    /// This is used for the internal tmpfs of the kernel.
     pub fn new() -> Arc<Self> {
         Arc::new(TmpFs {
             inner: RamFs::new(),
         })
     }
+
+    fn new_with_options(options: &TmpFsOptions) -> Result<Arc<Self>> {
+        // The default limits are consistent with Linux.
+        // Reference: <https://elixir.bootlin.com/linux/v6.16/source/mm/shmem.c#L152>
+        let default_max = crate::vm::mem_total() / PAGE_SIZE / 2;
+        let max_blocks = options.max_blocks.unwrap_or(default_max);
+        let max_inodes = options.max_inodes.unwrap_or(default_max);
+
+        let inner = RamFs::new_with_limits(to_limit(max_blocks), to_limit(max_inodes));
+
+        let root_inode = inner.root_inode();
+        if let Some(mode) = options.mode {
+            root_inode.set_mode(mode)?;
+        }
+        if let Some(uid) = options.uid {
+            root_inode.set_owner(uid)?;
+        }
+        if let Some(gid) = options.gid {
+            root_inode.set_group(gid)?;
+        }
+
+        Ok(Arc::new(TmpFs { inner }))
+    }
 }
 
 impl FileSystem for TmpFs {
@@ -44,7 +76,32 @@ impl FileSystem for TmpFs {
     }
 
     fn sb(&self) -> SuperBlock {
-        self.inner.sb()
+        let mut sb = self.inner.sb();
+        sb.magic = TMPFS_MAGIC;
+
+        // Unlimited resources are reported as zeros, which is consistent with Linux.
+        let limits = self.inner.limits();
+        if let Some(max_blocks) = limits.max_blocks() {
+            sb.blocks = max_blocks;
+            sb.bfree = max_blocks.saturating_sub(limits.used_blocks());
+            sb.bavail = sb.bfree;
+        }
+        if let Some(max_inodes) = limits.max_inodes() {
+            sb.files = max_inodes;
+            sb.ffree = max_inodes.saturating_sub(limits.used_inodes());
+        }
+
+        sb
+    }
+
+    fn set_fs_flags(&self, _flags: FsFlags, data: Option<CString>, _ctx: &Context) -> Result<()> {
+        let options = TmpFsOptions::parse(data.as_deref())?;
+
+        // Like Linux, the `mode`, `uid`, and `gid` options are ignored when remounting.
+        let limits = self.inner.limits();
+        let max_blocks = options.max_blocks.map_or(limits.max_blocks(), to_limit);
+        let max_inodes = options.max_inodes.map_or(limits.max_inodes(), to_limit);
+        limits.set_limits(max_blocks, max_inodes)
     }
 
     fn fs_event_subscriber_stats(&self) -> &FsEventSubscriberStats {
@@ -52,6 +109,98 @@ impl FileSystem for TmpFs {
     }
 }
 
+/// The mount options of tmpfs.
+///
+/// Reference: <https://man7.org/linux/man-pages/man5/tmpfs.5.html>
+#[derive(Debug, Default)]
+struct TmpFsOptions {
+    /// The maximum number of blocks, where zero means unlimited.
+    max_blocks: Option<usize>,
+    /// The maximum number of inodes, where zero means unlimited.
+    max_inodes: Option<usize>,
+    /// The permissions of the root directory.
+    mode: Option<InodeMode>,
+    /// The owner of the root directory.
+    uid: Option<Uid>,
+    /// The group of the root directory.
+    gid: Option<Gid>,
+}
+
+impl TmpFsOptions {
+    fn parse(args: Option<&CStr>) -> Result<Self> {
+        let mut options = Self::default();
+
+        let Some(args) = args else {
+            return Ok(options);
+        };
+        let args = args.to_string_lossy();
+
+        for entry in args.split(',').filter(|entry| !entry.is_empty()) {
+            let (key, value) = entry.split_once('=').unwrap_or((entry, ""));
+            match key {
+                "size" => {
+                    let size = if let Some(percent) = value.strip_suffix('%') {
+                        let percent = parse_number(percent)?;
+                        (crate::vm::mem_total() / 100)
+                            .checked_mul(percent)
+                            .ok_or_else(invalid_option)?
+                    } else {
+                        parse_size(value)?
+                    };
+                    options.max_blocks = Some(size.div_ceil(PAGE_SIZE));
+                }
+                "nr_blocks" => options.max_blocks = Some(parse_size(value)?),
+                "nr_inodes" => options.max_inodes = Some(parse_size(value)?),
+                "mode" => {
+                    let mode = u16::from_str_radix(value, 8).map_err(|_| invalid_option())?;
+                    options.mode = Some(InodeMode::from_bits_truncate(mode));
+                }
+                "uid" => options.uid = Some(Uid::new(parse_id(value)?)),
+                "gid" => options.gid = Some(Gid::new(parse_id(value)?)),
+                _ => return_errno_with_message!(Errno::EINVAL, "unknown tmpfs option"),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Converts a limit from the mount options, where zero means unlimited.
+fn to_limit(max: usize) -> Option<usize> {
+    if max == 0 { None } else { Some(max) }
+}
+
+/// Parses a number with an optional `k`, `m`, `g`, `t`, `p`, or `e` suffix.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.16/source/lib/cmdline.c#L152>
+fn parse_size(value: &str) -> Result<usize> {
+    let (number, shift) = match value.as_bytes().last().map(u8::to_ascii_lowercase) {
+        Some(b'k') => (&value[..value.len() - 1], 10),
+        Some(b'm') => (&value[..value.len() - 1], 20),
+        Some(b'g') => (&value[..value.len() - 1], 30),
+        Some(b't') => (&value[..value.len() - 1], 40),
+        Some(b'p') => (&value[..value.len() - 1], 50),
+        Some(b'e') => (&value[..value.len() - 1], 60),
+        _ => (value, 0),
+    };
+
+    parse_number(number)?
+        .checked_mul(1 << shift)
+        .ok_or_else(invalid_option)
+}
+
+fn parse_number(value: &str) -> Result<usize> {
+    value.parse::<usize>().map_err(|_| invalid_option())
+}
+
+fn parse_id(value: &str) -> Result<u32> {
+    value.parse::<u32>().map_err(|_| invalid_option())
+}
+
+fn invalid_option() -> Error {
+    Error::with_message(Errno::EINVAL, "invalid tmpfs option value")
+}
+
 pub(super) struct TmpFsType;
 
 impl FsType for TmpFsType {
@@ -66,10 +215,13 @@ impl FsType for TmpFsType {
     fn create(
         &self,
         _flags: FsFlags,
-        _args: Option<CString>,
+        _source: Option<&str>,
+        args: Option<CString>,
         _disk: Option<Arc<dyn aster_block::BlockDevice>>,
     ) -> Result<Arc<dyn FileSystem>> {
-        Ok(TmpFs::new())
+        let options = TmpFsOptions::parse(args.as_deref())?;
+        let tmpfs = TmpFs::new_with_options(&options)?;
+        Ok(tmpfs)
     }
 
     fn sysnode(&self) -> Option<Arc<dyn aster_systree::SysNode>> {
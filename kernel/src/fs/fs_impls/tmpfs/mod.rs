@@ -7,7 +7,6 @@ use fs::TmpFsType;
 
 mod fs;
 
-#[expect(dead_code)]
 const TMPFS_MAGIC: u64 = 0x0102_1994;
 
 pub(super) fn init() {
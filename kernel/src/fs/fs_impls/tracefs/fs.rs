@@ -0,0 +1,111 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::sync::Arc;
+
+use aster_block::BlockDevice;
+use aster_systree::SysNode;
+use spin::Once;
+
+use super::inode::TraceInode;
+use crate::{
+    fs::{
+        Result,
+        pseudofs::AnonDeviceId,
+        tracefs::systree_node::TraceRootNode,
+        utils::systree_inode::SysTreeInodeTy,
+        vfs::{
+            file_system::{FileSystem, FsEventSubscriberStats, FsFlags, SuperBlock},
+            inode::Inode,
+            registry::{FsProperties, FsType},
+        },
+    },
+    prelude::*,
+};
+
+/// A file system that controls the tracepoints and exports the trace events.
+///
+/// `TraceFs` is a RAM-based file system whose files are generated from the tracepoints
+/// defined in [`crate::trace`].
+pub struct TraceFs {
+    _anon_device_id: AnonDeviceId,
+    sb: SuperBlock,
+    root: Arc<dyn Inode>,
+    fs_event_subscriber_stats: FsEventSubscriberStats,
+}
+
+// Magic number for `TraceFs` (taken from Linux).
+const MAGIC_NUMBER: u64 = 0x74726163;
+const BLOCK_SIZE: usize = 4096;
+const NAME_MAX: usize = 255;
+
+impl TraceFs {
+    /// Returns the `TraceFs` singleton.
+    pub(super) fn singleton() -> &'static Arc<TraceFs> {
+        static SINGLETON: Once<Arc<TraceFs>> = Once::new();
+
+        SINGLETON.call_once(|| Self::new(TraceRootNode::singleton().clone()))
+    }
+
+    fn new(root_node: Arc<TraceRootNode>) -> Arc<Self> {
+        let anon_device_id =
+            AnonDeviceId::acquire().expect("no device ID is available for tracefs");
+        let sb = SuperBlock::new(MAGIC_NUMBER, BLOCK_SIZE, NAME_MAX, anon_device_id.id());
+        let root_inode = TraceInode::new_root(root_node, &sb);
+
+        Arc::new(Self {
+            _anon_device_id: anon_device_id,
+            sb,
+            root: root_inode,
+            fs_event_subscriber_stats: FsEventSubscriberStats::new(),
+        })
+    }
+}
+
+impl FileSystem for TraceFs {
+    fn name(&self) -> &'static str {
+        "tracefs"
+    }
+
+    fn sync(&self) -> Result<()> {
+        // `TraceFs` is volatile, sync is a no-op
+        Ok(())
+    }
+
+    fn root_inode(&self) -> Arc<dyn Inode> {
+        self.root.clone()
+    }
+
+    fn sb(&self) -> SuperBlock {
+        self.sb.clone()
+    }
+
+    fn fs_event_subscriber_stats(&self) -> &FsEventSubscriberStats {
+        &self.fs_event_subscriber_stats
+    }
+}
+
+pub(super) struct TraceFsType;
+
+impl FsType for TraceFsType {
+    fn name(&self) -> &'static str {
+        "tracefs"
+    }
+
+    fn properties(&self) -> FsProperties {
+        FsProperties::empty()
+    }
+
+    fn create(
+        &self,
+        _flags: FsFlags,
+        _source: Option<&str>,
+        _args: Option<CString>,
+        _disk: Option<Arc<dyn BlockDevice>>,
+    ) -> Result<Arc<dyn FileSystem>> {
+        Ok(TraceFs::singleton().clone() as _)
+    }
+
+    fn sysnode(&self) -> Option<Arc<dyn SysNode>> {
+        None
+    }
+}
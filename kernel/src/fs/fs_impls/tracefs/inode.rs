@@ -0,0 +1,121 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::sync::{Arc, Weak};
+
+use ostd::sync::RwLock;
+
+use crate::{
+    fs::{
+        file::{AccessMode, FileIo, InodeMode, StatusFlags},
+        tracefs::{fs::TraceFs, trace_pipe::TracePipeHandle},
+        utils::systree_inode::{SysTreeInodeTy, SysTreeNodeKind},
+        vfs::{
+            file_system::FileSystem,
+            inode::{Extension, Inode, Metadata},
+        },
+    },
+    prelude::*,
+    trace::trace_buffer,
+};
+
+/// An inode abstraction used in the `TraceFs`.
+pub struct TraceInode {
+    /// The corresponding node in the SysTree.
+    node_kind: SysTreeNodeKind,
+    /// The metadata of this inode.
+    metadata: Metadata,
+    /// The extension of this inode.
+    extension: Extension,
+    /// The file mode (permissions) of this inode, protected by a lock.
+    mode: RwLock<InodeMode>,
+    /// Weak reference to the parent inode.
+    parent: Weak<TraceInode>,
+    /// Weak self-reference for cyclic data structures.
+    this: Weak<TraceInode>,
+}
+
+impl SysTreeInodeTy for TraceInode {
+    fn new_arc(
+        node_kind: SysTreeNodeKind,
+        metadata: Metadata,
+        mode: InodeMode,
+        parent: Weak<Self>,
+    ) -> Arc<Self>
+    where
+        Self: Sized,
+    {
+        Arc::new_cyclic(|this| Self {
+            node_kind,
+            metadata,
+            extension: Extension::new(),
+            mode: RwLock::new(mode),
+            parent,
+            this: this.clone(),
+        })
+    }
+
+    fn node_kind(&self) -> &SysTreeNodeKind {
+        &self.node_kind
+    }
+
+    fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    fn mode(&self) -> Result<InodeMode> {
+        Ok(*self.mode.read())
+    }
+
+    fn set_mode(&self, mode: InodeMode) -> Result<()> {
+        *self.mode.write() = mode;
+        Ok(())
+    }
+
+    fn parent(&self) -> &Weak<Self> {
+        &self.parent
+    }
+
+    fn this(&self) -> Arc<Self> {
+        self.this.upgrade().expect("Weak ref invalid")
+    }
+
+    fn extension(&self) -> &Extension {
+        &self.extension
+    }
+}
+
+impl Inode for TraceInode {
+    fn fs(&self) -> Arc<dyn FileSystem> {
+        TraceFs::singleton().clone()
+    }
+
+    fn resize(&self, new_size: usize) -> Result<()> {
+        // Like Linux, opening `trace` with `O_TRUNC` discards the recorded events.
+        if new_size == 0 && self.attr_name() == Some("trace") {
+            trace_buffer().clear();
+        }
+
+        Ok(())
+    }
+
+    fn open(
+        &self,
+        _access_mode: AccessMode,
+        _status_flags: StatusFlags,
+    ) -> Option<Result<Box<dyn FileIo>>> {
+        if self.attr_name() != Some("trace_pipe") {
+            return None;
+        }
+
+        Some(Ok(Box::new(TracePipeHandle)))
+    }
+}
+
+impl TraceInode {
+    fn attr_name(&self) -> Option<&str> {
+        match &self.node_kind {
+            SysTreeNodeKind::Attr(attr, _) => Some(attr.name().as_ref()),
+            _ => None,
+        }
+    }
+}
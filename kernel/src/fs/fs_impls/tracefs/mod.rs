@@ -0,0 +1,33 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Trace file system (tracefs).
+//!
+//! Tracefs controls the tracepoints defined in [`crate::trace`] and exports the recorded events.
+//! It contains the following files:
+//!  - `trace`, which shows the recorded events, and discards them if it is truncated or written;
+//!  - `trace_pipe`, which consumes the recorded events and blocks if there are none;
+//!  - `tracing_on`, which turns the recording of the events on and off;
+//!  - `available_events`, which lists the events as `<system>:<event>`;
+//!  - `events/enable`, `events/<system>/enable`, and `events/<system>/<event>/enable`,
+//!    which enable and disable all the events, the events of a subsystem, and an event;
+//!  - `events/<system>/<event>/id` and `events/<system>/<event>/format`, which describe an event.
+//!
+//! Tracefs is usually mounted at `/sys/kernel/tracing`.
+
+use aster_systree::EmptyNode;
+
+use crate::fs::tracefs::fs::TraceFsType;
+
+mod fs;
+mod inode;
+mod systree_node;
+mod trace_pipe;
+
+// This method should be called during kernel file system initialization,
+// _after_ `aster_systree::init`.
+pub(super) fn init() {
+    let tracing_kernel_sysnode = EmptyNode::new("tracing".into());
+    super::sysfs::register_kernel_sysnode(tracing_kernel_sysnode).unwrap();
+
+    crate::fs::vfs::registry::register(&TraceFsType).unwrap();
+}
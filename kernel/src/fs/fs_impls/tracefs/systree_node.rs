@@ -0,0 +1,276 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{
+    sync::{Arc, Weak},
+    vec,
+    vec::Vec,
+};
+use core::fmt::Debug;
+
+use aster_systree::{
+    BranchNodeFields, Error, MAX_ATTR_SIZE, Result, SysAttrSetBuilder, SysBranchNode, SysObj,
+    SysPerms, SysStr, inherit_sys_branch_node,
+};
+use aster_util::printer::VmPrinter;
+use inherit_methods_macro::inherit_methods;
+use ostd::{
+    cpu::num_cpus,
+    mm::{VmReader, VmWriter},
+};
+use spin::Once;
+
+use crate::trace::{TraceEvent, events::ALL_EVENTS, is_tracing_on, set_tracing_on, trace_buffer};
+
+/// The `SysTree` node that represents the root node of the `TraceFs`.
+#[derive(Debug)]
+pub struct TraceRootNode {
+    fields: BranchNodeFields<dyn SysObj, Self>,
+}
+
+#[inherit_methods(from = "self.fields")]
+impl TraceRootNode {
+    /// Returns the `TraceRootNode` singleton.
+    pub(super) fn singleton() -> &'static Arc<TraceRootNode> {
+        static SINGLETON: Once<Arc<TraceRootNode>> = Once::new();
+
+        SINGLETON.call_once(Self::new)
+    }
+
+    fn new() -> Arc<Self> {
+        let name = SysStr::from("tracing");
+
+        let mut builder = SysAttrSetBuilder::new();
+        builder.add(SysStr::from("trace"), SysPerms::DEFAULT_RW_ATTR_PERMS);
+        builder.add(SysStr::from("trace_pipe"), SysPerms::DEFAULT_RO_ATTR_PERMS);
+        builder.add(SysStr::from("tracing_on"), SysPerms::DEFAULT_RW_ATTR_PERMS);
+        builder.add(SysStr::from("available_events"), SysPerms::DEFAULT_RO_ATTR_PERMS);
+        let attrs = builder
+            .build()
+            .expect("Failed to build the attribute set of the tracefs root");
+
+        let root = Arc::new_cyclic(|weak_self| {
+            let fields = BranchNodeFields::new(name, attrs, weak_self.clone());
+            TraceRootNode { fields }
+        });
+        root.add_child(EventDir::new_events_root()).unwrap();
+
+        root
+    }
+
+    /// Adds a child node.
+    fn add_child(&self, new_child: Arc<dyn SysObj>) -> Result<()>;
+}
+
+inherit_sys_branch_node!(TraceRootNode, fields, {
+    fn is_root(&self) -> bool {
+        true
+    }
+
+    fn init_parent(&self, _parent: Weak<dyn SysBranchNode>) {
+        // This method should be a no-op for `RootNode`.
+    }
+
+    fn read_attr_at(&self, name: &str, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        match name {
+            "trace" => {
+                let (entries, written) = trace_buffer().stats();
+                writeln!(printer, "# tracer: nop")?;
+                writeln!(printer, "#")?;
+                writeln!(
+                    printer,
+                    "# entries-in-buffer/entries-written: {}/{}   #P:{}",
+                    entries,
+                    written,
+                    num_cpus()
+                )?;
+                writeln!(printer, "#")?;
+                writeln!(printer, "#           TASK-PID     CPU#     TIMESTAMP  FUNCTION")?;
+                writeln!(printer, "#              | |         |         |         |")?;
+
+                let mut result = Ok(());
+                trace_buffer().for_each(|record| {
+                    result = write!(printer, "{}", record);
+                    result.is_ok()
+                });
+                result?;
+            }
+            // `trace_pipe` is read with `TracePipeHandle`, which blocks if there are no events.
+            "trace_pipe" => (),
+            "tracing_on" => writeln!(printer, "{}", is_tracing_on() as u8)?,
+            "available_events" => {
+                for event in ALL_EVENTS {
+                    writeln!(printer, "{}:{}", event.system(), event.name())?;
+                }
+            }
+            _ => return Err(Error::AttributeError),
+        }
+
+        Ok(printer.bytes_written())
+    }
+
+    fn write_attr(&self, name: &str, reader: &mut VmReader) -> Result<usize> {
+        let (content, len) = reader
+            .read_cstring_until_end(MAX_ATTR_SIZE)
+            .map_err(|_| Error::PageFault)?;
+        let content = content.to_str().map_err(|_| Error::InvalidOperation)?;
+
+        match name {
+            // Like Linux, writing anything to `trace` discards the recorded events.
+            "trace" => trace_buffer().clear(),
+            "tracing_on" => set_tracing_on(parse_bool(content)?),
+            _ => return Err(Error::AttributeError),
+        }
+
+        Ok(len)
+    }
+
+    fn perms(&self) -> SysPerms {
+        // Like Linux, only the owner (i.e., root) can access tracefs by default.
+        SysPerms::OWNER_R | SysPerms::OWNER_W | SysPerms::OWNER_X
+    }
+});
+
+/// A directory under `events`, which controls a group of events.
+///
+/// The directory is one of the following:
+///  - The `events` directory, which controls all the events;
+///  - The directory of a subsystem, which controls the events of the subsystem;
+///  - The directory of an event.
+pub struct EventDir {
+    fields: BranchNodeFields<dyn SysObj, Self>,
+    /// The events that the `enable` file controls.
+    events: Vec<&'static TraceEvent>,
+    /// Whether this is the directory of an event, which has the `id` and `format` files.
+    is_event: bool,
+}
+
+#[inherit_methods(from = "self.fields")]
+impl EventDir {
+    fn new_events_root() -> Arc<Self> {
+        let root = Self::new("events", ALL_EVENTS.to_vec(), false);
+
+        let mut systems: Vec<&'static str> = Vec::new();
+        for event in ALL_EVENTS {
+            if !systems.contains(&event.system()) {
+                systems.push(event.system());
+            }
+        }
+
+        for system in systems {
+            let events: Vec<_> = ALL_EVENTS
+                .iter()
+                .copied()
+                .filter(|event| event.system() == system)
+                .collect();
+
+            let system_dir = Self::new(system, events.clone(), false);
+            for event in events {
+                system_dir
+                    .add_child(Self::new(event.name(), vec![event], true))
+                    .unwrap();
+            }
+            root.add_child(system_dir).unwrap();
+        }
+
+        root
+    }
+
+    fn new(name: &'static str, events: Vec<&'static TraceEvent>, is_event: bool) -> Arc<Self> {
+        let mut builder = SysAttrSetBuilder::new();
+        builder.add(SysStr::from("enable"), SysPerms::DEFAULT_RW_ATTR_PERMS);
+        if is_event {
+            builder.add(SysStr::from("id"), SysPerms::DEFAULT_RO_ATTR_PERMS);
+            builder.add(SysStr::from("format"), SysPerms::DEFAULT_RO_ATTR_PERMS);
+        }
+        let attrs = builder
+            .build()
+            .expect("Failed to build the attribute set of a tracefs event directory");
+
+        Arc::new_cyclic(|weak_self| {
+            let fields = BranchNodeFields::new(SysStr::from(name), attrs, weak_self.clone());
+            EventDir {
+                fields,
+                events,
+                is_event,
+            }
+        })
+    }
+
+    /// Adds a child node.
+    fn add_child(&self, new_child: Arc<dyn SysObj>) -> Result<()>;
+}
+
+impl Debug for EventDir {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("EventDir")
+            .field("name", self.fields.name())
+            .finish_non_exhaustive()
+    }
+}
+
+inherit_sys_branch_node!(EventDir, fields, {
+    fn read_attr_at(&self, name: &str, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut printer = VmPrinter::new_skip(writer, offset);
+
+        match name {
+            "enable" => {
+                // Like Linux, `X` means that only some of the events are enabled.
+                let nr_enabled = self.events.iter().filter(|event| event.is_enabled()).count();
+                let state = if nr_enabled == 0 {
+                    '0'
+                } else if nr_enabled == self.events.len() {
+                    '1'
+                } else {
+                    'X'
+                };
+                writeln!(printer, "{}", state)?;
+            }
+            "id" if self.is_event => writeln!(printer, "{}", self.events[0].id())?,
+            "format" if self.is_event => {
+                let event = self.events[0];
+                writeln!(printer, "name: {}", event.name())?;
+                writeln!(printer, "ID: {}", event.id())?;
+                writeln!(printer, "format:")?;
+                for (field, ty) in event.fields() {
+                    writeln!(printer, "\tfield:{} {};", ty, field)?;
+                }
+                writeln!(printer)?;
+                writeln!(printer, "print fmt: \"{}\"", event.print_fmt())?;
+            }
+            _ => return Err(Error::AttributeError),
+        }
+
+        Ok(printer.bytes_written())
+    }
+
+    fn write_attr(&self, name: &str, reader: &mut VmReader) -> Result<usize> {
+        if name != "enable" {
+            return Err(Error::AttributeError);
+        }
+
+        let (content, len) = reader
+            .read_cstring_until_end(MAX_ATTR_SIZE)
+            .map_err(|_| Error::PageFault)?;
+        let content = content.to_str().map_err(|_| Error::InvalidOperation)?;
+        let is_enabled = parse_bool(content)?;
+        for event in self.events.iter() {
+            event.set_enabled(is_enabled);
+        }
+
+        Ok(len)
+    }
+
+    fn perms(&self) -> SysPerms {
+        SysPerms::DEFAULT_RW_PERMS
+    }
+});
+
+fn parse_bool(content: &str) -> Result<bool> {
+    match content.trim() {
+        "0" => Ok(false),
+        "1" => Ok(true),
+        _ => Err(Error::InvalidOperation),
+    }
+}
@@ -0,0 +1,121 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use ostd::sync::WaitQueue;
+
+use crate::{
+    events::IoEvents,
+    fs::{
+        file::{FileIo, StatusFlags},
+        vfs::inode::InodeIo,
+    },
+    prelude::*,
+    process::signal::{Pause, PollHandle, Pollable},
+    trace::trace_buffer,
+};
+
+/// The interval to check whether new events have been recorded.
+///
+/// The tracepoints cannot wake up the readers (e.g., `sched_switch` fires with the run queue
+/// locked), so a blocked reader polls the trace buffer periodically.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A file handle opened from `/sys/kernel/tracing/trace_pipe`.
+///
+/// Unlike `trace`, reading `trace_pipe` consumes the events, and blocks if there are no events.
+pub(super) struct TracePipeHandle;
+
+impl TracePipeHandle {
+    /// Consumes the events that fit in the buffer and writes them to the buffer.
+    fn try_read(&self, writer: &mut VmWriter) -> Result<usize> {
+        let mut read_len = 0;
+        let mut result = Ok(());
+
+        trace_buffer().consume(|record| {
+            let line = record.to_string();
+            // An event that does not fit is kept for the next read, unless the buffer cannot
+            // hold even a single event, in which case the event is truncated.
+            if line.len() > writer.avail() && read_len > 0 {
+                return false;
+            }
+
+            match writer.write_fallible(&mut line.as_bytes().into()) {
+                Ok(len) => {
+                    read_len += len;
+                    true
+                }
+                Err(err) => {
+                    result = Err(err.into());
+                    false
+                }
+            }
+        });
+
+        result.map(|_| read_len)
+    }
+}
+
+impl Pollable for TracePipeHandle {
+    fn poll(&self, mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
+        let events = if trace_buffer().is_empty() {
+            IoEvents::empty()
+        } else {
+            IoEvents::IN
+        };
+        events & mask
+    }
+}
+
+impl InodeIo for TracePipeHandle {
+    fn read_at(
+        &self,
+        _offset: usize,
+        writer: &mut VmWriter,
+        status_flags: StatusFlags,
+    ) -> Result<usize> {
+        if writer.avail() == 0 {
+            return Ok(0);
+        }
+
+        let wait_queue = WaitQueue::new();
+        loop {
+            let read_len = self.try_read(writer)?;
+            if read_len > 0 {
+                return Ok(read_len);
+            }
+            if status_flags.contains(StatusFlags::O_NONBLOCK) {
+                return_errno_with_message!(Errno::EAGAIN, "no events have been recorded");
+            }
+
+            let result = wait_queue.pause_until_or_timeout(
+                || (!trace_buffer().is_empty()).then_some(()),
+                &POLL_INTERVAL,
+            );
+            match result {
+                Ok(()) => (),
+                Err(err) if err.error() == Errno::ETIME => (),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    fn write_at(
+        &self,
+        _offset: usize,
+        _reader: &mut VmReader,
+        _status_flags: StatusFlags,
+    ) -> Result<usize> {
+        return_errno_with_message!(Errno::EPERM, "`trace_pipe` is not writable");
+    }
+}
+
+impl FileIo for TracePipeHandle {
+    fn check_seekable(&self) -> Result<()> {
+        return_errno_with_message!(Errno::ESPIPE, "`trace_pipe` is not seekable");
+    }
+
+    fn is_offset_aware(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,519 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::format;
+use core::{ops::Range, time::Duration};
+
+use ostd::{const_assert, mm::VmIo};
+
+use super::{fat::ClusterId, utils::DosTime};
+use crate::{prelude::*, vm::vmo::Vmo};
+
+/// The size of a directory entry, which is also the size of a slot in a directory.
+pub(super) const DENTRY_SIZE: usize = 32;
+
+/// The maximum length of a long name in UTF-16 code units.
+pub(super) const MAX_NAME_LEN: usize = 255;
+
+/// The number of UTF-16 code units in an LFN entry.
+const LFN_CHARS_PER_DENTRY: usize = 13;
+/// The flag in the order of the LFN entry that holds the end of a long name.
+const LFN_LAST_FLAG: u8 = 0x40;
+const LFN_ORD_MASK: u8 = 0x3F;
+/// The attribute of LFN entries, i.e., `READ_ONLY | HIDDEN | SYSTEM | VOLUME_ID`.
+const LFN_ATTR: u8 = 0x0F;
+const LFN_ATTR_MASK: u8 = 0x3F;
+
+/// The first byte of a slot that is free.
+pub(super) const DELETED_MARK: u8 = 0xE5;
+/// The first byte of a slot that is free, as well as all the following slots.
+const END_MARK: u8 = 0x00;
+/// The first byte of a short name that starts with `DELETED_MARK`.
+const KANJI_MARK: u8 = 0x05;
+
+bitflags! {
+    /// The attributes of a short entry.
+    pub(super) struct FatAttr: u8 {
+        const READ_ONLY = 0x01;
+        const HIDDEN = 0x02;
+        const SYSTEM = 0x04;
+        const VOLUME_ID = 0x08;
+        const DIRECTORY = 0x10;
+        const ARCHIVE = 0x20;
+    }
+}
+
+bitflags! {
+    /// The flags that record the case of a short name, as introduced by Windows NT.
+    pub(super) struct CaseFlags: u8 {
+        /// The base name is in lowercase.
+        const LOWER_BASE = 0x08;
+        /// The extension is in lowercase.
+        const LOWER_EXT = 0x10;
+    }
+}
+
+/// A short entry, which names a file with an 8.3 name and describes it.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct RawDentry {
+    pub(super) name: [u8; 11],
+    pub(super) attr: u8,
+    pub(super) case_flags: u8,
+    pub(super) create_centis: u8,
+    pub(super) create_time: u16,
+    pub(super) create_date: u16,
+    pub(super) access_date: u16,
+    pub(super) cluster_high: u16,
+    pub(super) write_time: u16,
+    pub(super) write_date: u16,
+    pub(super) cluster_low: u16,
+    pub(super) size: u32,
+}
+
+const_assert!(size_of::<RawDentry>() == DENTRY_SIZE);
+
+impl RawDentry {
+    /// Creates a short entry whose timestamps are all `now`.
+    pub(super) fn new(short_name: &ShortName, attr: FatAttr, now: Duration) -> Self {
+        let now = DosTime::from_duration(now);
+        Self {
+            name: short_name.name,
+            attr: attr.bits(),
+            case_flags: short_name.case_flags.bits(),
+            create_centis: now.centis,
+            create_time: now.time,
+            create_date: now.date,
+            access_date: now.date,
+            cluster_high: 0,
+            write_time: now.time,
+            write_date: now.date,
+            cluster_low: 0,
+            size: 0,
+        }
+    }
+
+    /// Creates the `.` or `..` entry of a directory.
+    pub(super) fn new_dot(name: &[u8; 11], cluster: ClusterId, now: Duration) -> Self {
+        let short_name = ShortName {
+            name: *name,
+            case_flags: CaseFlags::empty(),
+        };
+        let mut dentry = Self::new(&short_name, FatAttr::DIRECTORY, now);
+        dentry.set_start_cluster(cluster);
+        dentry
+    }
+
+    pub(super) fn attr(&self) -> FatAttr {
+        FatAttr::from_bits_truncate(self.attr)
+    }
+
+    pub(super) fn short_name(&self) -> ShortName {
+        ShortName {
+            name: self.name,
+            case_flags: CaseFlags::from_bits_truncate(self.case_flags),
+        }
+    }
+
+    /// Returns the first cluster, or zero if no clusters are allocated.
+    ///
+    /// The high 16 bits are only used by FAT32.
+    pub(super) fn start_cluster(&self, is_fat32: bool) -> ClusterId {
+        let high = if is_fat32 { self.cluster_high } else { 0 };
+        ((high as ClusterId) << 16) | self.cluster_low as ClusterId
+    }
+
+    pub(super) fn set_start_cluster(&mut self, cluster: ClusterId) {
+        self.cluster_high = (cluster >> 16) as u16;
+        self.cluster_low = cluster as u16;
+    }
+}
+
+/// An LFN entry, which holds 13 UTF-16 code units of a long name.
+///
+/// A long name is stored in LFN entries in the reverse order, which are followed by
+/// the short entry of the file.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct RawLfnDentry {
+    ord: u8,
+    name1: [u16; 5],
+    attr: u8,
+    type_: u8,
+    checksum: u8,
+    name2: [u16; 6],
+    cluster_low: u16,
+    name3: [u16; 2],
+}
+
+const_assert!(size_of::<RawLfnDentry>() == DENTRY_SIZE);
+
+impl RawLfnDentry {
+    fn chars(&self) -> [u16; LFN_CHARS_PER_DENTRY] {
+        let (name1, name2, name3) = (self.name1, self.name2, self.name3);
+        let mut chars = [0; LFN_CHARS_PER_DENTRY];
+        chars[..5].copy_from_slice(&name1);
+        chars[5..11].copy_from_slice(&name2);
+        chars[11..].copy_from_slice(&name3);
+        chars
+    }
+}
+
+/// Encodes a long name into LFN entries in their on-disk order.
+pub(super) fn lfn_dentries(name: &str, checksum: u8) -> Vec<RawLfnDentry> {
+    let mut chars: Vec<u16> = name.encode_utf16().collect();
+    let nr_dentries = chars.len().div_ceil(LFN_CHARS_PER_DENTRY);
+    // The name is terminated by zero and padded with `0xFFFF` unless it fills the entries.
+    if chars.len() < nr_dentries * LFN_CHARS_PER_DENTRY {
+        chars.push(0);
+        chars.resize(nr_dentries * LFN_CHARS_PER_DENTRY, 0xFFFF);
+    }
+
+    (1..=nr_dentries)
+        .rev()
+        .map(|ord| {
+            let chars = &chars[(ord - 1) * LFN_CHARS_PER_DENTRY..ord * LFN_CHARS_PER_DENTRY];
+            let last_flag = if ord == nr_dentries { LFN_LAST_FLAG } else { 0 };
+            RawLfnDentry {
+                ord: ord as u8 | last_flag,
+                name1: chars[..5].try_into().unwrap(),
+                attr: LFN_ATTR,
+                type_: 0,
+                checksum,
+                name2: chars[5..11].try_into().unwrap(),
+                cluster_low: 0,
+                name3: chars[11..].try_into().unwrap(),
+            }
+        })
+        .collect()
+}
+
+/// Returns the number of LFN entries needed by a long name.
+pub(super) fn nr_lfn_dentries(name: &str) -> usize {
+    name.encode_utf16().count().div_ceil(LFN_CHARS_PER_DENTRY)
+}
+
+/// An 8.3 short name with its case flags.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) struct ShortName {
+    /// The base name and the extension in uppercase, which are padded with spaces.
+    pub(super) name: [u8; 11],
+    pub(super) case_flags: CaseFlags,
+}
+
+impl ShortName {
+    pub(super) const DOT: [u8; 11] = *b".          ";
+    pub(super) const DOTDOT: [u8; 11] = *b"..         ";
+
+    /// Returns the short name that represents `name` exactly, so no long name is needed.
+    ///
+    /// This requires `name` to be a valid 8.3 name whose base name and extension are
+    /// each in a single case.
+    pub(super) fn from_exact(name: &str) -> Option<Self> {
+        let (base, ext) = name.split_once('.').unwrap_or((name, ""));
+        if base.is_empty() || base.len() > 8 || ext.len() > 3 || ext.contains('.') {
+            return None;
+        }
+        if name.ends_with('.') {
+            return None;
+        }
+
+        let mut short_name = [b' '; 11];
+        let mut case_flags = CaseFlags::empty();
+        let (base_dst, ext_dst) = short_name.split_at_mut(8);
+        for (part, dst, lower_flag) in [
+            (base, base_dst, CaseFlags::LOWER_BASE),
+            (ext, ext_dst, CaseFlags::LOWER_EXT),
+        ] {
+            if !part.bytes().all(is_short_name_char) {
+                return None;
+            }
+            let has_lower = part.bytes().any(|b| b.is_ascii_lowercase());
+            let has_upper = part.bytes().any(|b| b.is_ascii_uppercase());
+            if has_lower && has_upper {
+                return None;
+            }
+            if has_lower {
+                case_flags |= lower_flag;
+            }
+            for (dst, b) in dst.iter_mut().zip(part.bytes()) {
+                *dst = b.to_ascii_uppercase();
+            }
+        }
+
+        Some(Self {
+            name: short_name,
+            case_flags,
+        })
+    }
+
+    /// Generates the basis of the short name of `name`, to which a numeric tail
+    /// should be appended.
+    ///
+    /// The spaces and the leading dots are removed, the characters that are invalid
+    /// in short names are replaced with underscores, and the base name and the extension
+    /// are truncated to 8 and 3 characters.
+    pub(super) fn basis(name: &str) -> [u8; 11] {
+        let name = name.trim_start_matches('.');
+        let (base, ext) = name.rsplit_once('.').unwrap_or((name, ""));
+        let convert = |c: char| match c {
+            ' ' | '.' => None,
+            c if c.is_ascii() && is_short_name_char(c as u8) => {
+                Some((c as u8).to_ascii_uppercase())
+            }
+            _ => Some(b'_'),
+        };
+
+        let mut basis = [b' '; 11];
+        for (dst, b) in basis[..8].iter_mut().zip(base.chars().filter_map(convert)) {
+            *dst = b;
+        }
+        for (dst, b) in basis[8..].iter_mut().zip(ext.chars().filter_map(convert)) {
+            *dst = b;
+        }
+        if basis[0] == b' ' {
+            basis[0] = b'_';
+        }
+        basis
+    }
+
+    /// Appends the numeric tail `~n` to a basis, e.g., `LONGFI~1.TXT`.
+    pub(super) fn with_numeric_tail(basis: &[u8; 11], n: u32) -> Self {
+        let tail = format!("~{n}");
+        let base_len = basis[..8]
+            .iter()
+            .rposition(|&b| b != b' ')
+            .map_or(0, |pos| pos + 1);
+        let prefix_len = base_len.min(8 - tail.len());
+
+        let mut name = *basis;
+        name[prefix_len..prefix_len + tail.len()].copy_from_slice(tail.as_bytes());
+        name[prefix_len + tail.len()..8].fill(b' ');
+        Self {
+            name,
+            case_flags: CaseFlags::empty(),
+        }
+    }
+
+    /// Returns the checksum that binds the LFN entries to the short entry.
+    pub(super) fn checksum(&self) -> u8 {
+        checksum(&self.name)
+    }
+
+    /// Returns the name that is shown to users.
+    ///
+    /// The bytes beyond ASCII are decoded as ISO-8859-1.
+    pub(super) fn display_name(&self) -> String {
+        let mut name = self.name;
+        if name[0] == KANJI_MARK {
+            name[0] = DELETED_MARK;
+        }
+        let decode = |part: &[u8], is_lower: bool| -> String {
+            let len = part
+                .iter()
+                .rposition(|&b| b != b' ')
+                .map_or(0, |pos| pos + 1);
+            part[..len]
+                .iter()
+                .map(|&b| {
+                    if is_lower {
+                        b.to_ascii_lowercase() as char
+                    } else {
+                        b as char
+                    }
+                })
+                .collect()
+        };
+
+        let mut display_name = decode(&name[..8], self.case_flags.contains(CaseFlags::LOWER_BASE));
+        let ext = decode(&name[8..], self.case_flags.contains(CaseFlags::LOWER_EXT));
+        if !ext.is_empty() {
+            display_name.push('.');
+            display_name.push_str(&ext);
+        }
+        display_name
+    }
+}
+
+fn is_short_name_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"$%'-_@~`!(){}^#&".contains(&b)
+}
+
+fn checksum(name: &[u8; 11]) -> u8 {
+    name.iter()
+        .fold(0u8, |sum, &b| sum.rotate_right(1).wrapping_add(b))
+}
+
+/// Checks a long name and strips its trailing dots, which are ignored as on Windows.
+pub(super) fn check_name(name: &str) -> Result<&str> {
+    let name = name.trim_end_matches('.');
+    if name.is_empty() {
+        return_errno_with_message!(Errno::EINVAL, "the name consists of dots");
+    }
+    if name.encode_utf16().count() > MAX_NAME_LEN {
+        return_errno_with_message!(Errno::ENAMETOOLONG, "the name is too long");
+    }
+    if name
+        .chars()
+        .any(|c| (c as u32) < 0x20 || "\"*/:<>?\\|".contains(c))
+    {
+        return_errno_with_message!(Errno::EINVAL, "the name contains invalid characters");
+    }
+    Ok(name)
+}
+
+/// Returns whether two names are the same, ignoring the case.
+pub(super) fn names_equal(a: &str, b: &str) -> bool {
+    a.chars()
+        .flat_map(char::to_uppercase)
+        .eq(b.chars().flat_map(char::to_uppercase))
+}
+
+/// An entry of a directory, which consists of the LFN entries and the short entry of a file.
+#[derive(Debug)]
+pub(super) struct DirEntry {
+    /// The long name, or the short name if there is no valid long name.
+    pub(super) name: String,
+    pub(super) dentry: RawDentry,
+    /// The slot of the first LFN entry, or of the short entry if there is no long name.
+    pub(super) first_slot: usize,
+    /// The slot of the short entry.
+    pub(super) slot: usize,
+}
+
+impl DirEntry {
+    /// Returns whether the entry is named `name`, by either its long or short name.
+    pub(super) fn matches(&self, name: &str) -> bool {
+        names_equal(&self.name, name) || names_equal(&self.dentry.short_name().display_name(), name)
+    }
+
+    /// Returns the slots occupied by the entry.
+    pub(super) fn slots(&self) -> Range<usize> {
+        self.first_slot..self.slot + 1
+    }
+}
+
+/// An iterator over the entries of a directory.
+///
+/// The `.` and `..` entries and the volume label are skipped.
+pub(super) struct DirEntryIter<'a> {
+    pages: &'a Vmo,
+    slot: usize,
+    nr_slots: usize,
+}
+
+impl<'a> DirEntryIter<'a> {
+    /// Creates an iterator from the slot `start_slot` of the directory of `size` bytes.
+    pub(super) fn new(pages: &'a Vmo, start_slot: usize, size: usize) -> Self {
+        Self {
+            pages,
+            slot: start_slot,
+            nr_slots: size / DENTRY_SIZE,
+        }
+    }
+
+    fn next_entry(&mut self) -> Result<Option<DirEntry>> {
+        // The long name being assembled, with the slot of its first LFN entry.
+        let mut long_name: Option<LongName> = None;
+
+        while self.slot < self.nr_slots {
+            let slot = self.slot;
+            let dentry = self.pages.read_val::<RawDentry>(slot * DENTRY_SIZE)?;
+            if dentry.name[0] == END_MARK {
+                self.slot = self.nr_slots;
+                break;
+            }
+            self.slot += 1;
+
+            if dentry.name[0] == DELETED_MARK {
+                long_name = None;
+                continue;
+            }
+            if dentry.attr & LFN_ATTR_MASK == LFN_ATTR {
+                let lfn_dentry = RawLfnDentry::from_bytes(dentry.as_bytes());
+                long_name = LongName::feed(long_name, &lfn_dentry, slot);
+                continue;
+            }
+
+            let short_name = dentry.short_name();
+            let long_name = long_name
+                .take()
+                .and_then(|long_name| long_name.finish(short_name.checksum()));
+            if dentry.attr().contains(FatAttr::VOLUME_ID) || dentry.name[0] == b'.' {
+                continue;
+            }
+
+            let (name, first_slot) = long_name.unwrap_or_else(|| (short_name.display_name(), slot));
+            return Ok(Some(DirEntry {
+                name,
+                dentry,
+                first_slot,
+                slot,
+            }));
+        }
+
+        Ok(None)
+    }
+}
+
+impl Iterator for DirEntryIter<'_> {
+    type Item = Result<DirEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_entry().transpose()
+    }
+}
+
+/// A long name that is assembled from the LFN entries.
+struct LongName {
+    chars: Vec<u16>,
+    checksum: u8,
+    /// The order of the next expected LFN entry.
+    next_ord: u8,
+    first_slot: usize,
+}
+
+impl LongName {
+    /// Feeds an LFN entry, returning `None` if the entries are out of order.
+    fn feed(long_name: Option<Self>, dentry: &RawLfnDentry, slot: usize) -> Option<Self> {
+        let ord = dentry.ord & LFN_ORD_MASK;
+        if ord == 0 {
+            return None;
+        }
+
+        if dentry.ord & LFN_LAST_FLAG != 0 {
+            let mut chars = vec![0; ord as usize * LFN_CHARS_PER_DENTRY];
+            let start = (ord as usize - 1) * LFN_CHARS_PER_DENTRY;
+            chars[start..].copy_from_slice(&dentry.chars());
+            return Some(Self {
+                chars,
+                checksum: dentry.checksum,
+                next_ord: ord - 1,
+                first_slot: slot,
+            });
+        }
+
+        let mut long_name = long_name?;
+        if ord != long_name.next_ord || dentry.checksum != long_name.checksum {
+            return None;
+        }
+        let start = (ord as usize - 1) * LFN_CHARS_PER_DENTRY;
+        long_name.chars[start..start + LFN_CHARS_PER_DENTRY].copy_from_slice(&dentry.chars());
+        long_name.next_ord = ord - 1;
+        Some(long_name)
+    }
+
+    /// Returns the name and the slot of its first LFN entry if the long name is complete
+    /// and belongs to the short entry with `checksum`.
+    fn finish(self, checksum: u8) -> Option<(String, usize)> {
+        if self.next_ord != 0 || self.checksum != checksum {
+            return None;
+        }
+        let len = self
+            .chars
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(self.chars.len());
+        let name = String::from_utf16(&self.chars[..len]).ok()?;
+        (!name.is_empty()).then_some((name, self.first_slot))
+    }
+}
@@ -0,0 +1,327 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::ops::Range;
+
+use ostd::mm::VmIo;
+
+use super::super_block::{FatType, VfatSuperBlock};
+use crate::{prelude::*, vm::vmo::Vmo};
+
+/// The index of a data cluster.
+pub(super) type ClusterId = u32;
+
+/// The first data cluster, as the first two FAT entries are reserved.
+pub(super) const FIRST_CLUSTER: ClusterId = 2;
+
+/// An entry of the file allocation table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum FatEntry {
+    Free,
+    Next(ClusterId),
+    Bad,
+    EndOfChain,
+}
+
+/// The file allocation table (FAT), which links the clusters of each file into a chain.
+///
+/// The table is accessed through the page cache of the filesystem. All the copies of
+/// the table are updated on writing unless mirroring is disabled.
+pub(super) struct Fat {
+    fat_type: FatType,
+    offset: usize,
+    size: usize,
+    num_fats: usize,
+    active_fat: Option<usize>,
+    num_clusters: u32,
+    pages: Arc<Vmo>,
+    /// The allocation state, whose lock also serializes the updates of the table.
+    ///
+    /// The updates must be serialized as the FAT12 entries of adjacent clusters share bytes.
+    state: Mutex<AllocState>,
+}
+
+#[derive(Debug)]
+struct AllocState {
+    free_count: u32,
+    next_free: ClusterId,
+}
+
+impl Fat {
+    pub(super) fn new(super_block: &VfatSuperBlock, pages: Arc<Vmo>) -> Self {
+        Self {
+            fat_type: super_block.fat_type,
+            offset: super_block.fat_offset,
+            size: super_block.fat_size,
+            num_fats: super_block.num_fats,
+            active_fat: super_block.active_fat,
+            num_clusters: super_block.num_clusters,
+            pages,
+            state: Mutex::new(AllocState {
+                free_count: 0,
+                next_free: FIRST_CLUSTER,
+            }),
+        }
+    }
+
+    /// Loads the allocation hints from the FSInfo sector.
+    ///
+    /// The free clusters are counted if `free_count` is unknown or invalid.
+    pub(super) fn load_alloc_hints(&self, free_count: u32, next_free: u32) -> Result<()> {
+        let free_count = if free_count <= self.num_clusters {
+            free_count
+        } else {
+            self.count_free_clusters()?
+        };
+
+        let mut state = self.state.lock();
+        state.free_count = free_count;
+        if self.is_valid_cluster(next_free) {
+            state.next_free = next_free;
+        }
+        Ok(())
+    }
+
+    /// Returns the number of free clusters.
+    pub(super) fn free_count(&self) -> u32 {
+        self.state.lock().free_count
+    }
+
+    /// Returns the cluster from which to look for free clusters.
+    pub(super) fn next_free(&self) -> ClusterId {
+        self.state.lock().next_free
+    }
+
+    /// Returns the device range of all the copies of the table.
+    pub(super) fn device_range(&self) -> Range<usize> {
+        self.offset..self.offset + self.num_fats * self.size
+    }
+
+    /// Reads the entry of a cluster.
+    pub(super) fn read(&self, cluster: ClusterId) -> Result<FatEntry> {
+        let offset = self.table_offset(self.active_fat.unwrap_or(0));
+        let raw = match self.fat_type {
+            FatType::Fat12 => {
+                let value = self.pages.read_val::<u16>(offset + fat12_offset(cluster))?;
+                let value = if cluster % 2 == 1 {
+                    value >> 4
+                } else {
+                    value & 0x0FFF
+                };
+                value.into()
+            }
+            FatType::Fat16 => self
+                .pages
+                .read_val::<u16>(offset + cluster as usize * 2)?
+                .into(),
+            FatType::Fat32 => {
+                self.pages.read_val::<u32>(offset + cluster as usize * 4)? & 0x0FFF_FFFF
+            }
+        };
+        Ok(self.decode(raw))
+    }
+
+    /// Returns the clusters of the chain starting from `start`.
+    pub(super) fn chain(&self, start: ClusterId) -> Result<Vec<ClusterId>> {
+        let mut clusters = Vec::new();
+        let mut cluster = start;
+        loop {
+            if !self.is_valid_cluster(cluster) || clusters.len() >= self.num_clusters as usize {
+                return_errno_with_message!(Errno::EIO, "corrupted cluster chain");
+            }
+            clusters.push(cluster);
+
+            match self.read(cluster)? {
+                FatEntry::Next(next) => cluster = next,
+                FatEntry::EndOfChain => return Ok(clusters),
+                FatEntry::Free | FatEntry::Bad => {
+                    return_errno_with_message!(Errno::EIO, "corrupted cluster chain")
+                }
+            }
+        }
+    }
+
+    /// Allocates `count` clusters as a chain, and links it after `last` if it is given.
+    ///
+    /// The `last` cluster must be the end of an existing chain.
+    pub(super) fn alloc(&self, count: usize, last: Option<ClusterId>) -> Result<Vec<ClusterId>> {
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut state = self.state.lock();
+        if count > state.free_count as usize {
+            return_errno_with_message!(Errno::ENOSPC, "no free clusters");
+        }
+
+        let mut clusters = Vec::with_capacity(count);
+        let mut cursor = state.next_free;
+        let mut nr_scanned = 0;
+        while clusters.len() < count && nr_scanned < self.num_clusters {
+            if self.read(cursor)? == FatEntry::Free {
+                clusters.push(cursor);
+            }
+            cursor = if cursor + 1 < FIRST_CLUSTER + self.num_clusters {
+                cursor + 1
+            } else {
+                FIRST_CLUSTER
+            };
+            nr_scanned += 1;
+        }
+        if clusters.len() < count {
+            // The free count in the FSInfo sector was wrong.
+            state.free_count = clusters.len() as u32;
+            return_errno_with_message!(Errno::ENOSPC, "no free clusters");
+        }
+
+        // Terminates the new chain before linking it to the existing one.
+        for (idx, &cluster) in clusters.iter().enumerate() {
+            let entry = match clusters.get(idx + 1) {
+                Some(&next) => FatEntry::Next(next),
+                None => FatEntry::EndOfChain,
+            };
+            self.write(&mut state, cluster, entry)?;
+        }
+        if let Some(last) = last {
+            self.write(&mut state, last, FatEntry::Next(clusters[0]))?;
+        }
+
+        state.free_count -= count as u32;
+        state.next_free = cursor;
+        Ok(clusters)
+    }
+
+    /// Frees the clusters of a chain except the first `keep` ones.
+    ///
+    /// The chain is terminated at the last kept cluster.
+    pub(super) fn truncate(&self, clusters: &[ClusterId], keep: usize) -> Result<()> {
+        if keep >= clusters.len() {
+            return Ok(());
+        }
+
+        let mut state = self.state.lock();
+        if keep > 0 {
+            self.write(&mut state, clusters[keep - 1], FatEntry::EndOfChain)?;
+        }
+        for &cluster in &clusters[keep..] {
+            self.write(&mut state, cluster, FatEntry::Free)?;
+        }
+        state.free_count += (clusters.len() - keep) as u32;
+        Ok(())
+    }
+
+    /// Writes the entry of a cluster to the table(s) in use.
+    fn write(&self, _state: &mut AllocState, cluster: ClusterId, entry: FatEntry) -> Result<()> {
+        let value = self.encode(entry);
+        let fats = match self.active_fat {
+            Some(active_fat) => active_fat..active_fat + 1,
+            None => 0..self.num_fats,
+        };
+
+        for fat_idx in fats {
+            let offset = self.table_offset(fat_idx);
+            match self.fat_type {
+                FatType::Fat12 => {
+                    let offset = offset + fat12_offset(cluster);
+                    let old_value = self.pages.read_val::<u16>(offset)?;
+                    let new_value = if cluster % 2 == 1 {
+                        (old_value & 0x000F) | ((value as u16) << 4)
+                    } else {
+                        (old_value & 0xF000) | (value as u16 & 0x0FFF)
+                    };
+                    self.pages.write_val(offset, &new_value)?;
+                }
+                FatType::Fat16 => {
+                    self.pages
+                        .write_val(offset + cluster as usize * 2, &(value as u16))?;
+                }
+                FatType::Fat32 => {
+                    // The high 4 bits are reserved and must be preserved.
+                    let offset = offset + cluster as usize * 4;
+                    let old_value = self.pages.read_val::<u32>(offset)?;
+                    self.pages
+                        .write_val(offset, &((old_value & 0xF000_0000) | value))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn count_free_clusters(&self) -> Result<u32> {
+        let end = FIRST_CLUSTER + self.num_clusters;
+        let entry_size = match self.fat_type {
+            FatType::Fat12 => {
+                let mut count = 0;
+                for cluster in FIRST_CLUSTER..end {
+                    if self.read(cluster)? == FatEntry::Free {
+                        count += 1;
+                    }
+                }
+                return Ok(count);
+            }
+            FatType::Fat16 => 2,
+            FatType::Fat32 => 4,
+        };
+
+        let offset = self.table_offset(self.active_fat.unwrap_or(0));
+        let mut buf = vec![0u8; PAGE_SIZE];
+        let mut count = 0;
+        let mut cluster = FIRST_CLUSTER;
+        while cluster < end {
+            let nr_entries = ((end - cluster) as usize).min(PAGE_SIZE / entry_size);
+            let buf = &mut buf[..nr_entries * entry_size];
+            self.pages
+                .read_bytes(offset + cluster as usize * entry_size, buf)?;
+            count += buf
+                .chunks_exact(entry_size)
+                .filter(|entry| match entry_size {
+                    2 => entry == &[0, 0],
+                    _ => entry[..3] == [0, 0, 0] && entry[3] & 0x0F == 0,
+                })
+                .count() as u32;
+            cluster += nr_entries as u32;
+        }
+        Ok(count)
+    }
+
+    fn decode(&self, raw: u32) -> FatEntry {
+        let (bad, min_end_of_chain) = match self.fat_type {
+            FatType::Fat12 => (0xFF7, 0xFF8),
+            FatType::Fat16 => (0xFFF7, 0xFFF8),
+            FatType::Fat32 => (0x0FFF_FFF7, 0x0FFF_FFF8),
+        };
+        match raw {
+            0 => FatEntry::Free,
+            raw if raw == bad => FatEntry::Bad,
+            raw if raw >= min_end_of_chain => FatEntry::EndOfChain,
+            raw => FatEntry::Next(raw),
+        }
+    }
+
+    fn encode(&self, entry: FatEntry) -> u32 {
+        let (bad, end_of_chain) = match self.fat_type {
+            FatType::Fat12 => (0xFF7, 0xFFF),
+            FatType::Fat16 => (0xFFF7, 0xFFFF),
+            FatType::Fat32 => (0x0FFF_FFF7, 0x0FFF_FFFF),
+        };
+        match entry {
+            FatEntry::Free => 0,
+            FatEntry::Next(next) => next,
+            FatEntry::Bad => bad,
+            FatEntry::EndOfChain => end_of_chain,
+        }
+    }
+
+    fn table_offset(&self, fat_idx: usize) -> usize {
+        self.offset + fat_idx * self.size
+    }
+
+    fn is_valid_cluster(&self, cluster: ClusterId) -> bool {
+        (FIRST_CLUSTER..FIRST_CLUSTER + self.num_clusters).contains(&cluster)
+    }
+}
+
+/// Returns the offset of a FAT12 entry, which is 12 bits long.
+fn fat12_offset(cluster: ClusterId) -> usize {
+    cluster as usize + cluster as usize / 2
+}
@@ -0,0 +1,377 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::ops::Range;
+
+use aster_block::{
+    BlockDevice,
+    bio::{Bio, BioDirection, BioSegment, BioType, BioWaiter},
+    id::Sid,
+};
+use ostd::mm::{Segment, VmIo, io::util::HasVmReaderWriter};
+
+use super::{
+    dentry::MAX_NAME_LEN,
+    fat::Fat,
+    inode::VfatInode,
+    super_block::{FS_INFO_UNKNOWN, RawFsInfo, VFAT_MAGIC, VfatSuperBlock},
+};
+use crate::{
+    fs::vfs::{
+        file_system::{FileSystem, FsEventSubscriberStats, FsFlags, SuperBlock},
+        inode::Inode,
+        page_cache::{CachePage, PageCache, PageCacheBackend},
+        registry::{FsProperties, FsType},
+    },
+    prelude::*,
+    process::{Gid, Uid},
+};
+
+/// The inode number of the root directory.
+///
+/// The inode numbers of the other files are the device offsets of their short entries
+/// in units of entries, which cannot be one as the first sector is the boot sector.
+pub(super) const ROOT_INO: u64 = 1;
+
+/// A FAT filesystem.
+pub struct VfatFs {
+    block_device: Arc<dyn BlockDevice>,
+    super_block: VfatSuperBlock,
+    fat: Fat,
+    /// The page cache of the reserved sectors and the FATs.
+    meta_cache: PageCache,
+    options: VfatMountOptions,
+    /// The cached inodes, indexed by the positions of their short entries.
+    ///
+    /// An inode stays in the cache until it is removed from its directory, so there
+    /// is at most one inode for each file.
+    inodes: Mutex<BTreeMap<u64, Arc<VfatInode>>>,
+    /// The lock that serializes the modifications of the directories.
+    dir_lock: Mutex<()>,
+    fs_event_subscriber_stats: FsEventSubscriberStats,
+}
+
+impl VfatFs {
+    /// Opens a FAT filesystem on the block device.
+    pub(super) fn open(
+        block_device: Arc<dyn BlockDevice>,
+        options: VfatMountOptions,
+    ) -> Result<Arc<Self>> {
+        let super_block = VfatSuperBlock::read(block_device.as_ref())?;
+        let fs = Arc::new_cyclic(|weak_self: &Weak<Self>| {
+            let meta_cache =
+                PageCache::with_capacity(super_block.fat_end(), weak_self.clone() as _).unwrap();
+            let fat = Fat::new(&super_block, meta_cache.pages().clone());
+            Self {
+                block_device,
+                super_block,
+                fat,
+                meta_cache,
+                options,
+                inodes: Mutex::new(BTreeMap::new()),
+                dir_lock: Mutex::new(()),
+                fs_event_subscriber_stats: FsEventSubscriberStats::new(),
+            }
+        });
+
+        let (free_count, next_free) = match fs.read_fs_info()? {
+            Some(fs_info) => (fs_info.free_count, fs_info.next_free),
+            None => (FS_INFO_UNKNOWN, FS_INFO_UNKNOWN),
+        };
+        fs.fat.load_alloc_hints(free_count, next_free)?;
+
+        let root = VfatInode::new_root(&fs)?;
+        fs.inodes.lock().insert(ROOT_INO, root);
+
+        Ok(fs)
+    }
+
+    pub(super) fn block_device(&self) -> &dyn BlockDevice {
+        self.block_device.as_ref()
+    }
+
+    pub(super) fn super_block(&self) -> &VfatSuperBlock {
+        &self.super_block
+    }
+
+    pub(super) fn fat(&self) -> &Fat {
+        &self.fat
+    }
+
+    pub(super) fn options(&self) -> &VfatMountOptions {
+        &self.options
+    }
+
+    /// Locks the directories for modification.
+    pub(super) fn lock_dirs(&self) -> MutexGuard<'_, ()> {
+        self.dir_lock.lock()
+    }
+
+    pub(super) fn root(&self) -> Arc<VfatInode> {
+        self.inodes.lock().get(&ROOT_INO).unwrap().clone()
+    }
+
+    /// Returns the cached inode whose short entry is at `pos`.
+    pub(super) fn cached_inode(&self, pos: u64) -> Option<Arc<VfatInode>> {
+        self.inodes.lock().get(&pos).cloned()
+    }
+
+    pub(super) fn cache_inode(&self, pos: u64, inode: Arc<VfatInode>) {
+        self.inodes.lock().insert(pos, inode);
+    }
+
+    pub(super) fn uncache_inode(&self, pos: u64) {
+        self.inodes.lock().remove(&pos);
+    }
+
+    /// Writes the allocation tables and the FSInfo sector back to the device.
+    pub(super) fn sync_meta(&self) -> Result<()> {
+        if let Some(mut fs_info) = self.read_fs_info()? {
+            fs_info.free_count = self.fat.free_count();
+            fs_info.next_free = self.fat.next_free();
+            self.meta_cache
+                .pages()
+                .write_val(self.super_block.fs_info_offset.unwrap(), &fs_info)?;
+        }
+        self.meta_cache.evict_range(0..self.super_block.fat_end())
+    }
+
+    fn read_fs_info(&self) -> Result<Option<RawFsInfo>> {
+        let Some(offset) = self.super_block.fs_info_offset else {
+            return Ok(None);
+        };
+        let fs_info = self.meta_cache.pages().read_val::<RawFsInfo>(offset)?;
+        Ok(fs_info.is_valid().then_some(fs_info))
+    }
+
+    /// Reads a page from the device ranges, which are mapped to the page in order.
+    ///
+    /// The rest of the page that is not covered by the ranges is filled with zeros.
+    pub(super) fn read_device_page(
+        &self,
+        ranges: &[Range<usize>],
+        frame: &CachePage,
+    ) -> Result<BioWaiter> {
+        if ranges.len() == 1 && ranges[0].len() == PAGE_SIZE {
+            return self.submit_page_bio(BioType::Read, ranges[0].start, frame);
+        }
+
+        // The fragmented pages are read synchronously.
+        let mut buf = vec![0u8; PAGE_SIZE];
+        let mut pos = 0;
+        for range in ranges {
+            self.block_device
+                .read_bytes(range.start, &mut buf[pos..pos + range.len()])?;
+            pos += range.len();
+        }
+        frame.writer().write(&mut VmReader::from(buf.as_slice()));
+        Ok(BioWaiter::new())
+    }
+
+    /// Writes a page to the device ranges, which are mapped to the page in order.
+    pub(super) fn write_device_page(
+        &self,
+        ranges: &[Range<usize>],
+        frame: &CachePage,
+    ) -> Result<BioWaiter> {
+        if ranges.len() == 1 && ranges[0].len() == PAGE_SIZE {
+            return self.submit_page_bio(BioType::Write, ranges[0].start, frame);
+        }
+
+        let mut buf = vec![0u8; PAGE_SIZE];
+        frame.reader().read(&mut VmWriter::from(buf.as_mut_slice()));
+        let mut bio_waiter = BioWaiter::new();
+        let mut pos = 0;
+        for range in ranges {
+            let waiter = self
+                .block_device
+                .write_bytes_async(range.start, &buf[pos..pos + range.len()])?;
+            bio_waiter.concat(waiter);
+            pos += range.len();
+        }
+        Ok(bio_waiter)
+    }
+
+    /// Submits a bio of a whole page, which may start at any sector of the device.
+    fn submit_page_bio(
+        &self,
+        type_: BioType,
+        offset: usize,
+        frame: &CachePage,
+    ) -> Result<BioWaiter> {
+        let direction = match type_ {
+            BioType::Read => BioDirection::FromDevice,
+            _ => BioDirection::ToDevice,
+        };
+        let bio_segment =
+            BioSegment::new_from_segment(Segment::from(frame.clone()).into(), direction);
+        let bio = Bio::new(type_, Sid::from_offset(offset), vec![bio_segment], None);
+        Ok(bio.submit(self.block_device.as_ref())?)
+    }
+
+    /// Returns the device range of a page in the page cache of the metadata.
+    ///
+    /// The range does not go beyond the FATs, as the root directory and the data
+    /// clusters are cached by the inodes.
+    fn meta_page_range(&self, idx: usize) -> Result<Range<usize>> {
+        let start = idx * PAGE_SIZE;
+        let end = (start + PAGE_SIZE).min(self.super_block.fat_end());
+        if start >= end {
+            return_errno_with_message!(Errno::EINVAL, "the page is beyond the FATs");
+        }
+        Ok(start..end)
+    }
+}
+
+impl PageCacheBackend for VfatFs {
+    fn read_page_async(&self, idx: usize, frame: &CachePage) -> Result<BioWaiter> {
+        let range = self.meta_page_range(idx)?;
+        self.read_device_page(&[range], frame)
+    }
+
+    fn write_page_async(&self, idx: usize, frame: &CachePage) -> Result<BioWaiter> {
+        let range = self.meta_page_range(idx)?;
+        self.write_device_page(&[range], frame)
+    }
+
+    fn npages(&self) -> usize {
+        self.super_block.fat_end().div_ceil(PAGE_SIZE)
+    }
+}
+
+impl FileSystem for VfatFs {
+    fn name(&self) -> &'static str {
+        "vfat"
+    }
+
+    fn sync(&self) -> Result<()> {
+        let inodes: Vec<_> = self.inodes.lock().values().cloned().collect();
+        // The entries must be updated before the directories are written back.
+        for inode in inodes.iter() {
+            inode.write_dentry()?;
+        }
+        for inode in inodes.iter() {
+            inode.flush_page_cache()?;
+        }
+        self.sync_meta()?;
+        self.block_device.sync()?;
+        Ok(())
+    }
+
+    fn root_inode(&self) -> Arc<dyn Inode> {
+        self.root()
+    }
+
+    fn sb(&self) -> SuperBlock {
+        let mut sb = SuperBlock::new(
+            VFAT_MAGIC,
+            self.super_block.cluster_size,
+            MAX_NAME_LEN,
+            self.block_device.id(),
+        );
+        sb.blocks = self.super_block.num_clusters as usize;
+        sb.bfree = self.fat.free_count() as usize;
+        sb.bavail = sb.bfree;
+        sb.fsid = self.super_block.volume_id as u64;
+        sb
+    }
+
+    fn fs_event_subscriber_stats(&self) -> &FsEventSubscriberStats {
+        &self.fs_event_subscriber_stats
+    }
+}
+
+/// The mount options of a FAT filesystem.
+#[derive(Debug, Clone)]
+pub(super) struct VfatMountOptions {
+    /// The owner of all the files.
+    pub(super) uid: Uid,
+    /// The group of all the files.
+    pub(super) gid: Gid,
+    /// The permission bits that are cleared from the regular files.
+    pub(super) fmask: u16,
+    /// The permission bits that are cleared from the directories.
+    pub(super) dmask: u16,
+}
+
+impl Default for VfatMountOptions {
+    fn default() -> Self {
+        Self {
+            uid: Uid::new_root(),
+            gid: Gid::new_root(),
+            fmask: 0o022,
+            dmask: 0o022,
+        }
+    }
+}
+
+impl VfatMountOptions {
+    fn parse(args: Option<&CStr>) -> Result<Self> {
+        let mut options = Self::default();
+
+        let Some(args) = args else {
+            return Ok(options);
+        };
+        let args = args.to_string_lossy();
+
+        for entry in args.split(',').filter(|entry| !entry.is_empty()) {
+            let (key, value) = entry.split_once('=').unwrap_or((entry, ""));
+            match key {
+                "uid" => options.uid = Uid::new(parse_id(value)?),
+                "gid" => options.gid = Gid::new(parse_id(value)?),
+                "umask" => {
+                    let mask = parse_mask(value)?;
+                    options.fmask = mask;
+                    options.dmask = mask;
+                }
+                "fmask" => options.fmask = parse_mask(value)?,
+                "dmask" => options.dmask = parse_mask(value)?,
+                // Names are always encoded in UTF-8.
+                "utf8" => {}
+                "iocharset" if value == "utf8" => {}
+                _ => return_errno_with_message!(Errno::EINVAL, "unknown vfat option"),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+fn parse_id(value: &str) -> Result<u32> {
+    value.parse::<u32>().map_err(|_| invalid_option())
+}
+
+fn parse_mask(value: &str) -> Result<u16> {
+    let mask = u16::from_str_radix(value, 8).map_err(|_| invalid_option())?;
+    Ok(mask & 0o777)
+}
+
+fn invalid_option() -> Error {
+    Error::with_message(Errno::EINVAL, "invalid vfat option value")
+}
+
+pub(super) struct VfatType;
+
+impl FsType for VfatType {
+    fn name(&self) -> &'static str {
+        "vfat"
+    }
+
+    fn properties(&self) -> FsProperties {
+        FsProperties::NEED_DISK
+    }
+
+    fn create(
+        &self,
+        _flags: FsFlags,
+        _source: Option<&str>,
+        args: Option<CString>,
+        disk: Option<Arc<dyn BlockDevice>>,
+    ) -> Result<Arc<dyn FileSystem>> {
+        let options = VfatMountOptions::parse(args.as_deref())?;
+        VfatFs::open(disk.unwrap(), options).map(|fs| fs as _)
+    }
+
+    fn sysnode(&self) -> Option<Arc<dyn aster_systree::SysNode>> {
+        None
+    }
+}
@@ -0,0 +1,1131 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::{ops::Range, time::Duration};
+
+use aster_block::{SECTOR_SIZE, bio::BioWaiter};
+use ostd::mm::VmIo;
+
+use super::{
+    dentry::{
+        DELETED_MARK, DENTRY_SIZE, DirEntry, DirEntryIter, END_MARK, FatAttr, RawDentry, ShortName,
+        check_name, lfn_dentries,
+    },
+    fat::ClusterId,
+    fs::{ROOT_INO, VfatFs, VfatMountOptions},
+    super_block::FatType,
+    utils::{DosTime, now},
+};
+use crate::{
+    fs::{
+        file::{InodeMode, InodeType, StatusFlags},
+        utils::DirentVisitor,
+        vfs::{
+            file_system::FileSystem,
+            inode::{Extension, Inode, InodeIo, Metadata},
+            page_cache::{CachePage, PageCache, PageCacheBackend},
+            path::is_dot_or_dotdot,
+        },
+    },
+    prelude::*,
+    process::{Gid, Uid},
+    vm::vmo::Vmo,
+};
+
+/// The maximum size of a regular file, which is limited by the 32-bit size field.
+const MAX_FILE_SIZE: usize = u32::MAX as usize;
+
+/// The maximum size of a directory, which can hold at most 65536 entries.
+const MAX_DIR_SIZE: usize = 65536 * DENTRY_SIZE;
+
+/// The maximum number of the numeric tails tried for a short name.
+const MAX_NUMERIC_TAIL: u32 = 999_999;
+
+/// An inode of a FAT filesystem.
+///
+/// FAT has no on-disk inodes. A file is described by its short entry in the parent
+/// directory, which is only updated when the file is synchronized.
+///
+/// The locks are acquired in the order of the directory lock of the filesystem,
+/// `inner`, and `clusters`.
+pub(super) struct VfatInode {
+    ino: u64,
+    type_: InodeType,
+    /// The clusters of the file, which are empty for an empty file or a fixed root directory.
+    clusters: RwMutex<Vec<ClusterId>>,
+    /// Whether the inode is the root directory of FAT12 or FAT16, which is stored in a
+    /// fixed region before the data clusters.
+    is_fixed_root: bool,
+    page_cache: PageCache,
+    inner: RwMutex<InodeInner>,
+    fs: Weak<VfatFs>,
+    this: Weak<Self>,
+    extension: Extension,
+}
+
+struct InodeInner {
+    /// The location of the short entry, which is `None` for the root directory or a
+    /// removed file.
+    location: Option<Location>,
+    attr: FatAttr,
+    /// The file size, or the allocated size for a directory.
+    size: usize,
+    atime: Duration,
+    mtime: Duration,
+    /// The status change time, which is not stored on the disk.
+    ctime: Duration,
+    nr_subdirs: usize,
+    is_deleted: bool,
+}
+
+struct Location {
+    parent: Arc<VfatInode>,
+    /// The slot of the short entry in the parent directory.
+    slot: usize,
+}
+
+impl VfatInode {
+    fn new(
+        fs: &Arc<VfatFs>,
+        ino: u64,
+        type_: InodeType,
+        clusters: Vec<ClusterId>,
+        is_fixed_root: bool,
+        inner: InodeInner,
+    ) -> Arc<Self> {
+        Arc::new_cyclic(|weak_self: &Weak<Self>| Self {
+            ino,
+            type_,
+            clusters: RwMutex::new(clusters),
+            is_fixed_root,
+            page_cache: PageCache::with_capacity(inner.size, weak_self.clone() as _).unwrap(),
+            inner: RwMutex::new(inner),
+            fs: Arc::downgrade(fs),
+            this: weak_self.clone(),
+            extension: Extension::new(),
+        })
+    }
+
+    /// Creates the root directory.
+    pub(super) fn new_root(fs: &Arc<VfatFs>) -> Result<Arc<Self>> {
+        let super_block = fs.super_block();
+        let (clusters, is_fixed_root, size) = if super_block.fat_type == FatType::Fat32 {
+            let clusters = fs.fat().chain(super_block.root_cluster)?;
+            let size = clusters.len() * super_block.cluster_size;
+            (clusters, false, size)
+        } else {
+            (Vec::new(), true, super_block.root_dir_size)
+        };
+        if size > MAX_DIR_SIZE {
+            return_errno_with_message!(Errno::EIO, "the root directory is too large");
+        }
+
+        let inner = InodeInner {
+            location: None,
+            attr: FatAttr::DIRECTORY,
+            size,
+            atime: Duration::ZERO,
+            mtime: Duration::ZERO,
+            ctime: Duration::ZERO,
+            nr_subdirs: 0,
+            is_deleted: false,
+        };
+        let root = Self::new(fs, ROOT_INO, InodeType::Dir, clusters, is_fixed_root, inner);
+        root.inner.write().nr_subdirs = root.count_subdirs()?;
+        Ok(root)
+    }
+
+    /// Loads the inode of an entry in the `parent` directory.
+    fn new_from_entry(fs: &Arc<VfatFs>, parent: Arc<Self>, entry: &DirEntry) -> Result<Arc<Self>> {
+        let super_block = fs.super_block();
+        let dentry = &entry.dentry;
+        let attr = dentry.attr();
+        let type_ = if attr.contains(FatAttr::DIRECTORY) {
+            InodeType::Dir
+        } else {
+            InodeType::File
+        };
+
+        let start_cluster = dentry.start_cluster(super_block.fat_type == FatType::Fat32);
+        let clusters = if start_cluster == 0 {
+            Vec::new()
+        } else {
+            fs.fat().chain(start_cluster)?
+        };
+        let allocated_size = clusters.len() * super_block.cluster_size;
+        let size = if type_ == InodeType::Dir {
+            if clusters.is_empty() || allocated_size > MAX_DIR_SIZE {
+                return_errno_with_message!(Errno::EIO, "invalid directory clusters");
+            }
+            allocated_size
+        } else {
+            let size = dentry.size as usize;
+            if size > allocated_size {
+                return_errno_with_message!(Errno::EIO, "the file size exceeds its clusters");
+            }
+            size
+        };
+
+        let mtime = DosTime {
+            date: dentry.write_date,
+            time: dentry.write_time,
+            centis: 0,
+        }
+        .as_duration();
+        let atime = DosTime {
+            date: dentry.access_date,
+            time: 0,
+            centis: 0,
+        }
+        .as_duration();
+
+        let slot = entry.slot;
+        let ino = parent.slot_pos(fs, slot);
+        let inner = InodeInner {
+            location: Some(Location { parent, slot }),
+            attr,
+            size,
+            atime,
+            mtime,
+            ctime: mtime,
+            nr_subdirs: 0,
+            is_deleted: false,
+        };
+        let inode = Self::new(fs, ino, type_, clusters, false, inner);
+        if type_ == InodeType::Dir {
+            inode.inner.write().nr_subdirs = inode.count_subdirs()?;
+        }
+        Ok(inode)
+    }
+
+    pub(super) fn fs(&self) -> Arc<VfatFs> {
+        self.fs.upgrade().unwrap()
+    }
+
+    fn this(&self) -> Arc<Self> {
+        self.this.upgrade().unwrap()
+    }
+
+    fn parent(&self) -> Option<Arc<Self>> {
+        let inner = self.inner.read();
+        inner
+            .location
+            .as_ref()
+            .map(|location| location.parent.clone())
+    }
+
+    /// Returns the size of the space allocated to the file.
+    fn allocated_size(&self, fs: &VfatFs) -> usize {
+        let super_block = fs.super_block();
+        if self.is_fixed_root {
+            super_block.root_dir_size
+        } else {
+            self.clusters.read().len() * super_block.cluster_size
+        }
+    }
+
+    /// Returns the device ranges that back a page in the page cache.
+    fn page_device_ranges(&self, idx: usize) -> Result<Vec<Range<usize>>> {
+        let fs = self.fs();
+        let super_block = fs.super_block();
+        let start = idx * PAGE_SIZE;
+
+        if self.is_fixed_root {
+            let end = (start + PAGE_SIZE).min(super_block.root_dir_size);
+            if start >= end {
+                return_errno_with_message!(Errno::EINVAL, "the page is beyond the directory");
+            }
+            let offset = super_block.root_dir_offset;
+            return Ok(vec![offset + start..offset + end]);
+        }
+
+        let cluster_size = super_block.cluster_size;
+        let clusters = self.clusters.read();
+        let end = (start + PAGE_SIZE).min(clusters.len() * cluster_size);
+        if start >= end {
+            return_errno_with_message!(Errno::EINVAL, "the page is beyond the clusters");
+        }
+
+        // Contiguous clusters are merged so that a whole page can be read at once.
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        let mut pos = start;
+        while pos < end {
+            let offset_in_cluster = pos % cluster_size;
+            let len = (cluster_size - offset_in_cluster).min(end - pos);
+            let device_start =
+                super_block.cluster_offset(clusters[pos / cluster_size]) + offset_in_cluster;
+            match ranges.last_mut() {
+                Some(last) if last.end == device_start => last.end += len,
+                _ => ranges.push(device_start..device_start + len),
+            }
+            pos += len;
+        }
+        Ok(ranges)
+    }
+
+    /// Returns the position of a slot, which is the device offset in units of entries.
+    fn slot_pos(&self, fs: &VfatFs, slot: usize) -> u64 {
+        let super_block = fs.super_block();
+        let offset = slot * DENTRY_SIZE;
+        let device_offset = if self.is_fixed_root {
+            super_block.root_dir_offset + offset
+        } else {
+            let clusters = self.clusters.read();
+            super_block.cluster_offset(clusters[offset / super_block.cluster_size])
+                + offset % super_block.cluster_size
+        };
+        (device_offset / DENTRY_SIZE) as u64
+    }
+
+    fn pages(&self) -> &Vmo {
+        self.page_cache.pages()
+    }
+
+    fn dir_entries(&self) -> DirEntryIter<'_> {
+        DirEntryIter::new(self.pages(), 0, self.inner.read().size)
+    }
+
+    fn find_entry(&self, name: &str) -> Result<Option<DirEntry>> {
+        for entry in self.dir_entries() {
+            let entry = entry?;
+            if entry.matches(name) {
+                return Ok(Some(entry));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the inode of an entry of the directory.
+    fn child_inode(&self, fs: &Arc<VfatFs>, entry: &DirEntry) -> Result<Arc<Self>> {
+        let pos = self.slot_pos(fs, entry.slot);
+        if let Some(inode) = fs.cached_inode(pos) {
+            return Ok(inode);
+        }
+
+        let inode = Self::new_from_entry(fs, self.this(), entry)?;
+        fs.cache_inode(pos, inode.clone());
+        Ok(inode)
+    }
+
+    /// Generates a short name for a new entry, and returns whether LFN entries are
+    /// needed to store the name.
+    fn new_short_name(&self, name: &str) -> Result<(ShortName, bool)> {
+        if let Some(short_name) = ShortName::from_exact(name) {
+            return Ok((short_name, false));
+        }
+
+        let mut existing_names = BTreeSet::new();
+        for entry in self.dir_entries() {
+            existing_names.insert(entry?.dentry.name);
+        }
+        let basis = ShortName::basis(name);
+        let short_name = (1..=MAX_NUMERIC_TAIL)
+            .map(|n| ShortName::with_numeric_tail(&basis, n))
+            .find(|short_name| !existing_names.contains(&short_name.name))
+            .ok_or_else(|| Error::with_message(Errno::EEXIST, "no short names are available"))?;
+        Ok((short_name, true))
+    }
+
+    /// Allocates `count` consecutive free slots and returns the first one.
+    fn alloc_slots(&self, fs: &VfatFs, count: usize) -> Result<usize> {
+        let nr_slots = self.inner.read().size / DENTRY_SIZE;
+        let mut run_start = 0;
+        // All the slots after the end mark are free.
+        let mut is_after_end = false;
+        for slot in 0..nr_slots {
+            if !is_after_end {
+                let mark = self.pages().read_val::<u8>(slot * DENTRY_SIZE)?;
+                if mark == END_MARK {
+                    is_after_end = true;
+                } else if mark != DELETED_MARK {
+                    run_start = slot + 1;
+                    continue;
+                }
+            }
+
+            if slot + 1 - run_start == count {
+                if is_after_end && slot + 1 < nr_slots {
+                    self.pages()
+                        .write_val((slot + 1) * DENTRY_SIZE, &END_MARK)?;
+                }
+                return Ok(run_start);
+            }
+        }
+
+        // The trailing free slots are used along with the new ones.
+        let nr_new_slots = count - (nr_slots - run_start);
+        self.extend_dir(fs, nr_new_slots * DENTRY_SIZE)?;
+        Ok(run_start)
+    }
+
+    fn extend_dir(&self, fs: &VfatFs, len: usize) -> Result<()> {
+        if self.is_fixed_root {
+            return_errno_with_message!(Errno::ENOSPC, "the root directory is full");
+        }
+
+        let cluster_size = fs.super_block().cluster_size;
+        let old_size = self.inner.read().size;
+        let new_size = old_size + len.div_ceil(cluster_size) * cluster_size;
+        if new_size > MAX_DIR_SIZE {
+            return_errno_with_message!(Errno::ENOSPC, "the directory is full");
+        }
+
+        self.alloc_clusters(fs, new_size)?;
+        self.page_cache.resize(new_size)?;
+        self.page_cache.fill_zeros(old_size..new_size)?;
+        self.inner.write().size = new_size;
+        Ok(())
+    }
+
+    /// Allocates the clusters so that `size` bytes can be stored.
+    fn alloc_clusters(&self, fs: &VfatFs, size: usize) -> Result<()> {
+        let nr_clusters = size.div_ceil(fs.super_block().cluster_size);
+        let mut clusters = self.clusters.write();
+        if nr_clusters <= clusters.len() {
+            return Ok(());
+        }
+
+        let new_clusters = fs
+            .fat()
+            .alloc(nr_clusters - clusters.len(), clusters.last().copied())?;
+        clusters.extend(new_clusters);
+        Ok(())
+    }
+
+    /// Adds an entry to the directory and returns the slot of its short entry.
+    fn add_entry(
+        &self,
+        fs: &VfatFs,
+        name: &str,
+        dentry: &RawDentry,
+        needs_long_name: bool,
+    ) -> Result<usize> {
+        let lfn_dentries = if needs_long_name {
+            lfn_dentries(name, dentry.short_name().checksum())
+        } else {
+            Vec::new()
+        };
+
+        let first_slot = self.alloc_slots(fs, lfn_dentries.len() + 1)?;
+        for (idx, lfn_dentry) in lfn_dentries.iter().enumerate() {
+            self.pages()
+                .write_val((first_slot + idx) * DENTRY_SIZE, lfn_dentry)?;
+        }
+        let slot = first_slot + lfn_dentries.len();
+        self.pages().write_val(slot * DENTRY_SIZE, dentry)?;
+        Ok(slot)
+    }
+
+    fn remove_entry(&self, slots: Range<usize>) -> Result<()> {
+        for slot in slots {
+            self.pages().write_val(slot * DENTRY_SIZE, &DELETED_MARK)?;
+        }
+        Ok(())
+    }
+
+    /// Updates a short entry with the attributes of the inode.
+    fn fill_dentry(&self, inner: &InodeInner, dentry: &mut RawDentry) {
+        dentry.attr = inner.attr.bits();
+        dentry.size = if self.type_ == InodeType::Dir {
+            0
+        } else {
+            inner.size as u32
+        };
+        dentry.set_start_cluster(self.clusters.read().first().copied().unwrap_or(0));
+
+        let mtime = DosTime::from_duration(inner.mtime);
+        dentry.write_date = mtime.date;
+        dentry.write_time = mtime.time;
+        dentry.access_date = DosTime::from_duration(inner.atime).date;
+    }
+
+    /// Writes the attributes of the inode to its short entry in the page cache of the
+    /// parent directory.
+    pub(super) fn write_dentry(&self) -> Result<()> {
+        let inner = self.inner.read();
+        let Some(location) = inner.location.as_ref() else {
+            return Ok(());
+        };
+
+        let offset = location.slot * DENTRY_SIZE;
+        let pages = location.parent.pages();
+        let mut dentry = pages.read_val::<RawDentry>(offset)?;
+        self.fill_dentry(&inner, &mut dentry);
+        pages.write_val(offset, &dentry)?;
+        Ok(())
+    }
+
+    pub(super) fn flush_page_cache(&self) -> Result<()> {
+        let size = self.inner.read().size;
+        self.page_cache.evict_range(0..size)
+    }
+
+    /// Returns the cluster referred to by the `..` entries of the subdirectories.
+    fn dotdot_cluster(&self) -> ClusterId {
+        // The root directory is always referred to as cluster zero.
+        if self.ino == ROOT_INO {
+            0
+        } else {
+            self.clusters.read()[0]
+        }
+    }
+
+    /// Writes the `.` and `..` entries of a new directory.
+    fn init_dir(&self, parent_cluster: ClusterId, now: Duration) -> Result<()> {
+        let size = self.inner.read().size;
+        self.page_cache.fill_zeros(0..size)?;
+
+        let cluster = self.clusters.read()[0];
+        self.pages()
+            .write_val(0, &RawDentry::new_dot(&ShortName::DOT, cluster, now))?;
+        self.pages().write_val(
+            DENTRY_SIZE,
+            &RawDentry::new_dot(&ShortName::DOTDOT, parent_cluster, now),
+        )?;
+        Ok(())
+    }
+
+    fn set_dotdot(&self, parent_cluster: ClusterId) -> Result<()> {
+        let mut dentry = self.pages().read_val::<RawDentry>(DENTRY_SIZE)?;
+        dentry.set_start_cluster(parent_cluster);
+        self.pages().write_val(DENTRY_SIZE, &dentry)?;
+        Ok(())
+    }
+
+    fn is_empty_dir(&self) -> Result<bool> {
+        Ok(self.dir_entries().next().transpose()?.is_none())
+    }
+
+    fn count_subdirs(&self) -> Result<usize> {
+        let mut nr_subdirs = 0;
+        for entry in self.dir_entries() {
+            if entry?.dentry.attr().contains(FatAttr::DIRECTORY) {
+                nr_subdirs += 1;
+            }
+        }
+        Ok(nr_subdirs)
+    }
+
+    /// Marks the inode as removed, whose clusters are freed when it is dropped.
+    fn mark_deleted(&self) {
+        let mut inner = self.inner.write();
+        inner.is_deleted = true;
+        inner.location = None;
+    }
+
+    fn touch(&self, now: Duration) {
+        let mut inner = self.inner.write();
+        inner.mtime = now;
+        inner.ctime = now;
+    }
+
+    fn make_mode(&self, options: &VfatMountOptions, attr: FatAttr) -> InodeMode {
+        let mode = if self.type_ == InodeType::Dir {
+            0o777 & !options.dmask
+        } else if attr.contains(FatAttr::READ_ONLY) {
+            0o555 & !options.fmask
+        } else {
+            0o777 & !options.fmask
+        };
+        InodeMode::from_bits_truncate(mode)
+    }
+
+    fn expand(&self, fs: &VfatFs, inner: &mut InodeInner, new_size: usize) -> Result<()> {
+        self.alloc_clusters(fs, new_size)?;
+        self.page_cache.resize(new_size)?;
+        self.page_cache.fill_zeros(inner.size..new_size)?;
+        inner.size = new_size;
+        Ok(())
+    }
+
+    fn shrink(&self, fs: &VfatFs, inner: &mut InodeInner, new_size: usize) -> Result<()> {
+        self.page_cache.resize(new_size)?;
+        inner.size = new_size;
+
+        let nr_kept = new_size.div_ceil(fs.super_block().cluster_size);
+        let mut clusters = self.clusters.write();
+        fs.fat().truncate(&clusters, nr_kept)?;
+        clusters.truncate(nr_kept);
+        Ok(())
+    }
+
+    pub(super) fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        if self.type_ != InodeType::File {
+            return_errno!(Errno::EISDIR);
+        }
+
+        let read_len = {
+            let inner = self.inner.read();
+            let start = inner.size.min(offset);
+            let end = inner.size.min(offset + writer.avail());
+            self.pages().read(start, writer)?;
+            end - start
+        };
+
+        self.set_atime(now());
+
+        Ok(read_len)
+    }
+
+    pub(super) fn write_at(&self, offset: usize, reader: &mut VmReader) -> Result<usize> {
+        if self.type_ != InodeType::File {
+            return_errno!(Errno::EISDIR);
+        }
+
+        let write_len = reader.remain();
+        if write_len == 0 {
+            return Ok(0);
+        }
+        let new_size = offset
+            .checked_add(write_len)
+            .filter(|new_size| *new_size <= MAX_FILE_SIZE)
+            .ok_or_else(|| Error::with_message(Errno::EFBIG, "the file is too large"))?;
+
+        let fs = self.fs();
+        let mut inner = self.inner.write();
+        if new_size > inner.size {
+            self.expand(&fs, &mut inner, new_size)?;
+        }
+        self.pages().write(offset, reader)?;
+
+        let now = now();
+        inner.mtime = now;
+        inner.ctime = now;
+        inner.attr |= FatAttr::ARCHIVE;
+
+        Ok(write_len)
+    }
+
+    pub(super) fn create(
+        &self,
+        name: &str,
+        type_: InodeType,
+        mode: InodeMode,
+    ) -> Result<Arc<VfatInode>> {
+        if self.type_ != InodeType::Dir {
+            return_errno!(Errno::ENOTDIR);
+        }
+        if type_ != InodeType::File && type_ != InodeType::Dir {
+            return_errno_with_message!(Errno::EPERM, "unsupported file type");
+        }
+        let name = check_name(name)?;
+
+        let fs = self.fs();
+        let _dir_guard = fs.lock_dirs();
+        if self.inner.read().is_deleted {
+            return_errno_with_message!(Errno::ENOENT, "the directory has been removed");
+        }
+        if self.find_entry(name)?.is_some() {
+            return_errno!(Errno::EEXIST);
+        }
+
+        let (short_name, needs_long_name) = self.new_short_name(name)?;
+        let now = now();
+        let attr = if type_ == InodeType::Dir {
+            FatAttr::DIRECTORY
+        } else if mode.bits() & 0o222 == 0 {
+            FatAttr::ARCHIVE | FatAttr::READ_ONLY
+        } else {
+            FatAttr::ARCHIVE
+        };
+        let mut dentry = RawDentry::new(&short_name, attr, now);
+
+        let clusters = if type_ == InodeType::Dir {
+            fs.fat().alloc(1, None)?
+        } else {
+            Vec::new()
+        };
+        if let Some(&cluster) = clusters.first() {
+            dentry.set_start_cluster(cluster);
+        }
+        let slot = match self.add_entry(&fs, name, &dentry, needs_long_name) {
+            Ok(slot) => slot,
+            Err(err) => {
+                fs.fat().truncate(&clusters, 0)?;
+                return Err(err);
+            }
+        };
+
+        let pos = self.slot_pos(&fs, slot);
+        let inner = InodeInner {
+            location: Some(Location {
+                parent: self.this(),
+                slot,
+            }),
+            attr,
+            size: clusters.len() * fs.super_block().cluster_size,
+            atime: now,
+            mtime: now,
+            ctime: now,
+            nr_subdirs: 0,
+            is_deleted: false,
+        };
+        let inode = Self::new(&fs, pos, type_, clusters, false, inner);
+        if type_ == InodeType::Dir {
+            inode.init_dir(self.dotdot_cluster(), now)?;
+            self.inner.write().nr_subdirs += 1;
+        }
+        self.touch(now);
+        fs.cache_inode(pos, inode.clone());
+
+        Ok(inode)
+    }
+
+    pub(super) fn lookup(&self, name: &str) -> Result<Arc<VfatInode>> {
+        if self.type_ != InodeType::Dir {
+            return_errno!(Errno::ENOTDIR);
+        }
+
+        let fs = self.fs();
+        let _dir_guard = fs.lock_dirs();
+        let entry = self
+            .find_entry(name.trim_end_matches('.'))?
+            .ok_or_else(|| Error::new(Errno::ENOENT))?;
+        self.child_inode(&fs, &entry)
+    }
+
+    pub(super) fn readdir_at(
+        &self,
+        offset: usize,
+        visitor: &mut dyn DirentVisitor,
+    ) -> Result<usize> {
+        if self.type_ != InodeType::Dir {
+            return_errno!(Errno::ENOTDIR);
+        }
+
+        let fs = self.fs();
+        let offset_read = {
+            let _dir_guard = fs.lock_dirs();
+            let parent_ino = self.parent().map_or(self.ino, |parent| parent.ino);
+
+            // The offsets 0 and 1 are `.` and `..`, and the offset `n + 2` is the slot `n`.
+            let try_readdir = |offset: &mut usize, visitor: &mut dyn DirentVisitor| -> Result<()> {
+                if *offset == 0 {
+                    visitor.visit(".", self.ino, InodeType::Dir, 1)?;
+                    *offset = 1;
+                }
+                if *offset == 1 {
+                    visitor.visit("..", parent_ino, InodeType::Dir, 2)?;
+                    *offset = 2;
+                }
+
+                let size = self.inner.read().size;
+                for entry in DirEntryIter::new(self.pages(), *offset - 2, size) {
+                    let entry = entry?;
+                    let pos = self.slot_pos(&fs, entry.slot);
+                    let ino = fs.cached_inode(pos).map_or(pos, |inode| inode.ino);
+                    let type_ = if entry.dentry.attr().contains(FatAttr::DIRECTORY) {
+                        InodeType::Dir
+                    } else {
+                        InodeType::File
+                    };
+                    let next_offset = entry.slot + 3;
+                    visitor.visit(&entry.name, ino, type_, next_offset)?;
+                    *offset = next_offset;
+                }
+
+                Ok(())
+            };
+
+            let mut iterate_offset = offset;
+            match try_readdir(&mut iterate_offset, visitor) {
+                Err(e) if iterate_offset == offset => Err(e),
+                _ => Ok(iterate_offset - offset),
+            }?
+        };
+
+        self.set_atime(now());
+
+        Ok(offset_read)
+    }
+
+    pub(super) fn unlink(&self, name: &str) -> Result<()> {
+        self.remove_child(name, InodeType::File)
+    }
+
+    pub(super) fn rmdir(&self, name: &str) -> Result<()> {
+        self.remove_child(name, InodeType::Dir)
+    }
+
+    fn remove_child(&self, name: &str, type_: InodeType) -> Result<()> {
+        if self.type_ != InodeType::Dir {
+            return_errno!(Errno::ENOTDIR);
+        }
+
+        let fs = self.fs();
+        let _dir_guard = fs.lock_dirs();
+        let entry = self
+            .find_entry(name.trim_end_matches('.'))?
+            .ok_or_else(|| Error::new(Errno::ENOENT))?;
+        let inode = self.child_inode(&fs, &entry)?;
+        match (type_, inode.type_) {
+            (InodeType::Dir, InodeType::Dir) => {
+                if !inode.is_empty_dir()? {
+                    return_errno!(Errno::ENOTEMPTY);
+                }
+            }
+            (InodeType::Dir, _) => return_errno!(Errno::ENOTDIR),
+            (_, InodeType::Dir) => return_errno!(Errno::EISDIR),
+            _ => {}
+        }
+
+        self.remove_entry(entry.slots())?;
+        inode.mark_deleted();
+        fs.uncache_inode(self.slot_pos(&fs, entry.slot));
+
+        if type_ == InodeType::Dir {
+            self.inner.write().nr_subdirs -= 1;
+        }
+        self.touch(now());
+
+        Ok(())
+    }
+
+    pub(super) fn rename(&self, old_name: &str, target: &VfatInode, new_name: &str) -> Result<()> {
+        if is_dot_or_dotdot(old_name) || is_dot_or_dotdot(new_name) {
+            return_errno!(Errno::EISDIR);
+        }
+        if self.type_ != InodeType::Dir || target.type_ != InodeType::Dir {
+            return_errno!(Errno::ENOTDIR);
+        }
+        let new_name = check_name(new_name)?;
+
+        let fs = self.fs();
+        let _dir_guard = fs.lock_dirs();
+        if target.inner.read().is_deleted {
+            return_errno_with_message!(Errno::ENOENT, "the directory has been removed");
+        }
+        let old_entry = self
+            .find_entry(old_name.trim_end_matches('.'))?
+            .ok_or_else(|| Error::new(Errno::ENOENT))?;
+        let inode = self.child_inode(&fs, &old_entry)?;
+
+        // A directory cannot be moved into its own subtree.
+        if inode.type_ == InodeType::Dir {
+            let mut dir = Some(target.this());
+            while let Some(current) = dir {
+                if Arc::ptr_eq(&current, &inode) {
+                    return_errno_with_message!(Errno::EINVAL, "cannot move into a subdirectory");
+                }
+                dir = current.parent();
+            }
+        }
+
+        if let Some(exist_entry) = target.find_entry(new_name)? {
+            let exist_inode = target.child_inode(&fs, &exist_entry)?;
+            if Arc::ptr_eq(&exist_inode, &inode) {
+                // Only the case of the name may be changed.
+                if exist_entry.name == new_name {
+                    return Ok(());
+                }
+            } else {
+                match (inode.type_, exist_inode.type_) {
+                    (InodeType::Dir, InodeType::Dir) => {
+                        if !exist_inode.is_empty_dir()? {
+                            return_errno!(Errno::ENOTEMPTY);
+                        }
+                    }
+                    (InodeType::Dir, _) => return_errno!(Errno::ENOTDIR),
+                    (_, InodeType::Dir) => return_errno!(Errno::EISDIR),
+                    _ => {}
+                }
+
+                target.remove_entry(exist_entry.slots())?;
+                exist_inode.mark_deleted();
+                fs.uncache_inode(target.slot_pos(&fs, exist_entry.slot));
+                if exist_inode.type_ == InodeType::Dir {
+                    target.inner.write().nr_subdirs -= 1;
+                }
+            }
+        }
+
+        // The old entry is restored if the new one cannot be added.
+        let old_slots = old_entry.slots();
+        let mut old_bytes = vec![0u8; old_slots.len() * DENTRY_SIZE];
+        self.pages()
+            .read_bytes(old_slots.start * DENTRY_SIZE, &mut old_bytes)?;
+        let old_pos = self.slot_pos(&fs, old_entry.slot);
+        self.remove_entry(old_slots.clone())?;
+
+        let add_new_entry = || -> Result<usize> {
+            let (short_name, needs_long_name) = target.new_short_name(new_name)?;
+            let mut dentry = old_entry.dentry;
+            dentry.name = short_name.name;
+            dentry.case_flags = short_name.case_flags.bits();
+            inode.fill_dentry(&inode.inner.read(), &mut dentry);
+            target.add_entry(&fs, new_name, &dentry, needs_long_name)
+        };
+        let new_slot = match add_new_entry() {
+            Ok(slot) => slot,
+            Err(err) => {
+                self.pages()
+                    .write_bytes(old_slots.start * DENTRY_SIZE, &old_bytes)?;
+                return Err(err);
+            }
+        };
+
+        fs.uncache_inode(old_pos);
+        fs.cache_inode(target.slot_pos(&fs, new_slot), inode.clone());
+        let now = now();
+        {
+            let mut inner = inode.inner.write();
+            inner.location = Some(Location {
+                parent: target.this(),
+                slot: new_slot,
+            });
+            inner.ctime = now;
+        }
+
+        if !core::ptr::eq(self, target) && inode.type_ == InodeType::Dir {
+            inode.set_dotdot(target.dotdot_cluster())?;
+            self.inner.write().nr_subdirs -= 1;
+            target.inner.write().nr_subdirs += 1;
+        }
+        self.touch(now);
+        target.touch(now);
+
+        Ok(())
+    }
+
+    pub(super) fn sync_all(&self) -> Result<()> {
+        let fs = self.fs();
+
+        self.flush_page_cache()?;
+        self.write_dentry()?;
+        if let Some(parent) = self.parent() {
+            parent.flush_page_cache()?;
+        }
+        fs.sync_meta()?;
+        fs.block_device().sync()?;
+
+        Ok(())
+    }
+}
+
+impl PageCacheBackend for VfatInode {
+    fn read_page_async(&self, idx: usize, frame: &CachePage) -> Result<BioWaiter> {
+        let ranges = self.page_device_ranges(idx)?;
+        self.fs().read_device_page(&ranges, frame)
+    }
+
+    fn write_page_async(&self, idx: usize, frame: &CachePage) -> Result<BioWaiter> {
+        let ranges = self.page_device_ranges(idx)?;
+        self.fs().write_device_page(&ranges, frame)
+    }
+
+    fn npages(&self) -> usize {
+        self.allocated_size(&self.fs()).div_ceil(PAGE_SIZE)
+    }
+}
+
+impl InodeIo for VfatInode {
+    fn read_at(
+        &self,
+        offset: usize,
+        writer: &mut VmWriter,
+        _status_flags: StatusFlags,
+    ) -> Result<usize> {
+        self.read_at(offset, writer)
+    }
+
+    fn write_at(
+        &self,
+        offset: usize,
+        reader: &mut VmReader,
+        _status_flags: StatusFlags,
+    ) -> Result<usize> {
+        self.write_at(offset, reader)
+    }
+}
+
+impl Inode for VfatInode {
+    fn size(&self) -> usize {
+        self.inner.read().size
+    }
+
+    fn resize(&self, new_size: usize) -> Result<()> {
+        if self.type_ != InodeType::File {
+            return_errno!(Errno::EISDIR);
+        }
+        if new_size > MAX_FILE_SIZE {
+            return_errno_with_message!(Errno::EFBIG, "the file is too large");
+        }
+
+        let fs = self.fs();
+        let mut inner = self.inner.write();
+        if new_size > inner.size {
+            self.expand(&fs, &mut inner, new_size)?;
+        } else if new_size < inner.size {
+            self.shrink(&fs, &mut inner, new_size)?;
+        }
+
+        Ok(())
+    }
+
+    fn metadata(&self) -> Metadata {
+        let fs = self.fs();
+        let options = fs.options();
+        let inner = self.inner.read();
+
+        let nr_hard_links = if self.type_ == InodeType::Dir {
+            inner.nr_subdirs + 2
+        } else {
+            1
+        };
+
+        Metadata {
+            ino: self.ino,
+            size: inner.size,
+            optimal_block_size: fs.super_block().cluster_size,
+            nr_sectors_allocated: self.allocated_size(&fs) / SECTOR_SIZE,
+            last_access_at: inner.atime,
+            last_modify_at: inner.mtime,
+            last_meta_change_at: inner.ctime,
+            type_: self.type_,
+            mode: self.make_mode(options, inner.attr),
+            nr_hard_links,
+            uid: options.uid,
+            gid: options.gid,
+            container_dev_id: fs.block_device().id(),
+            self_dev_id: None,
+        }
+    }
+
+    fn ino(&self) -> u64 {
+        self.ino
+    }
+
+    fn type_(&self) -> InodeType {
+        self.type_
+    }
+
+    fn mode(&self) -> Result<InodeMode> {
+        let attr = self.inner.read().attr;
+        Ok(self.make_mode(self.fs().options(), attr))
+    }
+
+    fn set_mode(&self, mode: InodeMode) -> Result<()> {
+        // Only the write permission of a regular file can be kept, by the read-only attribute.
+        let mut inner = self.inner.write();
+        if self.type_ == InodeType::File {
+            inner.attr.set(FatAttr::READ_ONLY, mode.bits() & 0o222 == 0);
+        }
+        inner.ctime = now();
+        Ok(())
+    }
+
+    fn owner(&self) -> Result<Uid> {
+        Ok(self.fs().options().uid)
+    }
+
+    fn set_owner(&self, uid: Uid) -> Result<()> {
+        if uid != self.fs().options().uid {
+            return_errno_with_message!(Errno::EPERM, "the owner cannot be changed");
+        }
+        Ok(())
+    }
+
+    fn group(&self) -> Result<Gid> {
+        Ok(self.fs().options().gid)
+    }
+
+    fn set_group(&self, gid: Gid) -> Result<()> {
+        if gid != self.fs().options().gid {
+            return_errno_with_message!(Errno::EPERM, "the group cannot be changed");
+        }
+        Ok(())
+    }
+
+    fn atime(&self) -> Duration {
+        self.inner.read().atime
+    }
+
+    fn set_atime(&self, time: Duration) {
+        self.inner.write().atime = time;
+    }
+
+    fn mtime(&self) -> Duration {
+        self.inner.read().mtime
+    }
+
+    fn set_mtime(&self, time: Duration) {
+        self.inner.write().mtime = time;
+    }
+
+    fn ctime(&self) -> Duration {
+        self.inner.read().ctime
+    }
+
+    fn set_ctime(&self, time: Duration) {
+        self.inner.write().ctime = time;
+    }
+
+    fn page_cache(&self) -> Option<Arc<Vmo>> {
+        if self.type_ != InodeType::File {
+            return None;
+        }
+        Some(self.page_cache.pages().clone())
+    }
+
+    fn create(&self, name: &str, type_: InodeType, mode: InodeMode) -> Result<Arc<dyn Inode>> {
+        Ok(self.create(name, type_, mode)?)
+    }
+
+    fn readdir_at(&self, offset: usize, visitor: &mut dyn DirentVisitor) -> Result<usize> {
+        self.readdir_at(offset, visitor)
+    }
+
+    fn unlink(&self, name: &str) -> Result<()> {
+        self.unlink(name)
+    }
+
+    fn rmdir(&self, name: &str) -> Result<()> {
+        self.rmdir(name)
+    }
+
+    fn lookup(&self, name: &str) -> Result<Arc<dyn Inode>> {
+        Ok(self.lookup(name)?)
+    }
+
+    fn rename(&self, old_name: &str, target: &Arc<dyn Inode>, new_name: &str) -> Result<()> {
+        let target = target
+            .downcast_ref::<VfatInode>()
+            .ok_or_else(|| Error::with_message(Errno::EXDEV, "not same fs"))?;
+        self.rename(old_name, target, new_name)
+    }
+
+    fn sync_all(&self) -> Result<()> {
+        self.sync_all()
+    }
+
+    fn sync_data(&self) -> Result<()> {
+        // The file size and the clusters are only recorded in the entry.
+        self.sync_all()
+    }
+
+    fn fs(&self) -> Arc<dyn FileSystem> {
+        self.fs()
+    }
+
+    fn extension(&self) -> &Extension {
+        &self.extension
+    }
+}
+
+impl Drop for VfatInode {
+    fn drop(&mut self) {
+        if !self.inner.read().is_deleted {
+            return;
+        }
+        let Some(fs) = self.fs.upgrade() else {
+            return;
+        };
+
+        // The dirty pages must not be written to the freed clusters.
+        self.page_cache
+            .discard_range(0..self.page_cache.pages().size());
+        let clusters = self.clusters.read();
+        if let Err(err) = fs.fat().truncate(&clusters, 0) {
+            warn!("failed to free the clusters of a removed file: {:?}", err);
+        }
+    }
+}
@@ -0,0 +1,362 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! A FAT12/FAT16/FAT32 filesystem with VFAT long file names.
+//!
+//! FAT is the filesystem of most USB sticks and SD cards, of the EFI system partitions,
+//! and of the disks that are shared with other operating systems.
+//!
+//! The features of this version of VFAT are as follows:
+//! 1. All three FAT variants. The variant is determined by the number of clusters,
+//!    and all the copies of the allocation table are kept in sync.
+//! 2. Long file names. A file is named by a sequence of UTF-16 LFN entries followed
+//!    by its 8.3 short entry, whose unique short name is generated on creation.
+//!    Names are looked up case-insensitively.
+//! 3. Deep integration with PageCache. The data of files and directories are stored
+//!    in the page caches of the inodes, while the allocation tables are stored in
+//!    the page cache of the filesystem.
+//! 4. DOS timestamps. The modification, access and creation times are converted from
+//!    and to the local DOS format, which is interpreted as UTC.
+//!
+//! The owners and the permissions of the files are given by the `uid`, `gid`, `umask`,
+//! `fmask` and `dmask` mount options, as FAT has no notion of them. Clearing all the
+//! write bits of a file sets its read-only attribute.
+//!
+//! # Limitation
+//!
+//! Here we summarizes the features that need to be implemented in the future.
+//! 1. Supports code pages other than ISO-8859-1 for the short names.
+//! 2. Maintains stable inode numbers. An inode number is derived from the position of
+//!    the directory entry, so a renamed file may share it with a file created later.
+//! 3. Handles the intermediate failure status correctly.
+
+pub use fs::VfatFs;
+
+use crate::fs::vfat::fs::VfatType;
+
+mod dentry;
+mod fat;
+mod fs;
+mod inode;
+mod super_block;
+mod utils;
+
+pub(super) fn init() {
+    crate::fs::vfs::registry::register(&VfatType).unwrap();
+}
+
+#[cfg(ktest)]
+mod test {
+    use alloc::fmt::Debug;
+
+    use aster_block::{
+        BlockDevice, BlockDeviceMeta, SECTOR_SIZE,
+        bio::{BioEnqueueError, BioStatus, BioType, SubmittedBio},
+    };
+    use device_id::DeviceId;
+    use ostd::{
+        mm::{FrameAllocOptions, PAGE_SIZE, Segment, VmIo, io::util::HasVmReaderWriter},
+        prelude::*,
+    };
+
+    use super::{
+        dentry::MAX_NAME_LEN,
+        fs::{VfatFs, VfatMountOptions},
+        super_block::FatType,
+    };
+    use crate::{
+        fs::{
+            file::{InodeMode, InodeType},
+            vfs::{file_system::FileSystem, inode::Inode},
+        },
+        prelude::*,
+    };
+
+    /// A block device whose sectors are kept in memory.
+    struct VfatMemoryDisk(Segment<()>);
+
+    impl Debug for VfatMemoryDisk {
+        fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+            f.debug_struct("VfatMemoryDisk")
+                .field("sectors_count", &(self.0.size() / SECTOR_SIZE))
+                .finish()
+        }
+    }
+
+    impl BlockDevice for VfatMemoryDisk {
+        fn enqueue(&self, bio: SubmittedBio) -> core::prelude::v1::Result<(), BioEnqueueError> {
+            let mut cur_device_ofs = bio.sid_range().start.to_raw() as usize * SECTOR_SIZE;
+            for seg in bio.segments() {
+                let size = match bio.type_() {
+                    BioType::Read => seg
+                        .inner_dma()
+                        .writer()
+                        .unwrap()
+                        .write(self.0.reader().skip(cur_device_ofs)),
+                    BioType::Write => self
+                        .0
+                        .writer()
+                        .skip(cur_device_ofs)
+                        .write(&mut seg.inner_dma().reader().unwrap()),
+                    _ => 0,
+                };
+                cur_device_ofs += size;
+            }
+            bio.complete(BioStatus::Complete);
+            Ok(())
+        }
+
+        fn metadata(&self) -> BlockDeviceMeta {
+            BlockDeviceMeta {
+                max_nr_segments_per_bio: usize::MAX,
+                nr_sectors: self.0.size() / SECTOR_SIZE,
+                supports_fua: false,
+                max_discard_sectors: 0,
+            }
+        }
+
+        fn name(&self) -> &str {
+            todo!()
+        }
+
+        fn id(&self) -> DeviceId {
+            todo!()
+        }
+    }
+
+    const FAT_TYPES: [FatType; 3] = [FatType::Fat12, FatType::Fat16, FatType::Fat32];
+
+    /// Formats a disk with one sector per cluster and two FATs.
+    ///
+    /// The numbers of clusters are the smallest ones that make FAT16 and FAT32 images.
+    fn format(fat_type: FatType) -> Arc<VfatMemoryDisk> {
+        const NUM_FATS: usize = 2;
+
+        let (reserved_sectors, root_entries, num_clusters, entry_bits) = match fat_type {
+            FatType::Fat12 => (1, 224, 2847, 12),
+            FatType::Fat16 => (4, 512, 4085, 16),
+            FatType::Fat32 => (32, 0, 65525, 32),
+        };
+        let fat_sectors = ((num_clusters + 2) * entry_bits)
+            .div_ceil(8)
+            .div_ceil(SECTOR_SIZE);
+        let root_dir_sectors = root_entries * 32 / SECTOR_SIZE;
+        let total_sectors =
+            reserved_sectors + NUM_FATS * fat_sectors + root_dir_sectors + num_clusters;
+
+        let segment = FrameAllocOptions::new()
+            .alloc_segment((total_sectors * SECTOR_SIZE).div_ceil(PAGE_SIZE))
+            .unwrap();
+
+        segment.write_val(11, &(SECTOR_SIZE as u16)).unwrap();
+        segment.write_val(13, &1u8).unwrap();
+        segment.write_val(14, &(reserved_sectors as u16)).unwrap();
+        segment.write_val(16, &(NUM_FATS as u8)).unwrap();
+        segment.write_val(17, &(root_entries as u16)).unwrap();
+        segment.write_val(21, &0xF8u8).unwrap();
+        if fat_type == FatType::Fat32 {
+            segment.write_val(32, &(total_sectors as u32)).unwrap();
+            segment.write_val(36, &(fat_sectors as u32)).unwrap();
+            // The root directory is the first cluster, and the FSInfo is the second sector.
+            segment.write_val(44, &2u32).unwrap();
+            segment.write_val(48, &1u16).unwrap();
+        } else {
+            segment.write_val(19, &(total_sectors as u16)).unwrap();
+            segment.write_val(22, &(fat_sectors as u16)).unwrap();
+        }
+        segment.write_val(510, &0xAA55u16).unwrap();
+
+        // The first two entries are reserved, and the third one ends the root directory of FAT32.
+        let reserved_entries: &[u8] = match fat_type {
+            FatType::Fat12 => &[0xF8, 0xFF, 0xFF],
+            FatType::Fat16 => &[0xF8, 0xFF, 0xFF, 0xFF],
+            FatType::Fat32 => &[
+                0xF8, 0xFF, 0xFF, 0x0F, 0xFF, 0xFF, 0xFF, 0x0F, 0xFF, 0xFF, 0xFF, 0x0F,
+            ],
+        };
+        for i in 0..NUM_FATS {
+            let offset = (reserved_sectors + i * fat_sectors) * SECTOR_SIZE;
+            segment.write_bytes(offset, reserved_entries).unwrap();
+        }
+
+        if fat_type == FatType::Fat32 {
+            segment.write_val(SECTOR_SIZE, &0x4161_5252u32).unwrap();
+            segment
+                .write_val(SECTOR_SIZE + 484, &0x6141_7272u32)
+                .unwrap();
+            segment
+                .write_val(SECTOR_SIZE + 488, &(num_clusters as u32 - 1))
+                .unwrap();
+            segment.write_val(SECTOR_SIZE + 492, &3u32).unwrap();
+            segment
+                .write_val(SECTOR_SIZE + 508, &0xAA55_0000u32)
+                .unwrap();
+        }
+
+        Arc::new(VfatMemoryDisk(segment))
+    }
+
+    fn mount(disk: &Arc<VfatMemoryDisk>) -> Arc<VfatFs> {
+        let fs = VfatFs::open(disk.clone(), VfatMountOptions::default());
+        assert!(fs.is_ok(), "Fs failed to init: {:?}", fs.unwrap_err());
+        fs.unwrap()
+    }
+
+    /// Writes everything back to the disk and mounts it again.
+    fn remount(fs: Arc<VfatFs>, disk: &Arc<VfatMemoryDisk>) -> Arc<VfatFs> {
+        fs.sync().unwrap();
+        drop(fs);
+        mount(disk)
+    }
+
+    fn list_names(dir: &Arc<dyn Inode>) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        dir.readdir_at(0, &mut names).unwrap();
+        names.retain(|name| name != "." && name != "..");
+        names.sort();
+        names
+    }
+
+    #[ktest]
+    fn fat_type() {
+        for fat_type in FAT_TYPES {
+            let fs = mount(&format(fat_type));
+            let super_block = fs.super_block();
+            assert_eq!(super_block.fat_type, fat_type);
+
+            // The root directory of FAT32 occupies a cluster.
+            let nr_used = if fat_type == FatType::Fat32 { 1 } else { 0 };
+            assert_eq!(fs.fat().free_count(), super_block.num_clusters - nr_used);
+        }
+    }
+
+    #[ktest]
+    fn write_and_read_back() {
+        for fat_type in FAT_TYPES {
+            let disk = format(fat_type);
+            let fs = mount(&disk);
+            let root = fs.root_inode();
+
+            let dir = root
+                .create("dir", InodeType::Dir, InodeMode::all())
+                .unwrap();
+            let file = dir
+                .create("data.bin", InodeType::File, InodeMode::all())
+                .unwrap();
+            assert!(
+                dir.create("DATA.BIN", InodeType::File, InodeMode::all())
+                    .is_err(),
+                "Fs created a file whose name differs only in case"
+            );
+
+            // The data span several clusters and do not end on a cluster boundary.
+            let data: Vec<u8> = (0..5000).map(|i| (i % 251) as u8).collect();
+            assert_eq!(file.write_bytes_at(0, &data).unwrap(), data.len());
+            assert_eq!(file.size(), data.len());
+
+            let mut buf = vec![0u8; data.len()];
+            assert_eq!(file.read_bytes_at(0, &mut buf).unwrap(), data.len());
+            assert_eq!(buf, data);
+
+            let fs = remount(fs, &disk);
+            let root = fs.root_inode();
+            let file = root.lookup("DIR").unwrap().lookup("Data.Bin").unwrap();
+            assert_eq!(file.type_(), InodeType::File);
+            assert_eq!(file.size(), data.len());
+
+            let mut buf = vec![0u8; data.len()];
+            assert_eq!(file.read_bytes_at(0, &mut buf).unwrap(), data.len());
+            assert_eq!(buf, data);
+
+            // Reading past the end stops at the end of the file.
+            let mut buf = vec![0u8; 1000];
+            assert_eq!(file.read_bytes_at(4500, &mut buf).unwrap(), 500);
+            assert_eq!(buf[..500], data[4500..]);
+        }
+    }
+
+    #[ktest]
+    fn long_name_round_trip() {
+        let mut names = vec![
+            "A Long File Name.with.several.dots".to_string(),
+            "MiXeD.Txt".to_string(),
+            "short.txt".to_string(),
+            "überlänge dateiname.txt".to_string(),
+            "x".repeat(MAX_NAME_LEN),
+        ];
+        names.sort();
+
+        for fat_type in FAT_TYPES {
+            let disk = format(fat_type);
+            let fs = mount(&disk);
+            let root = fs.root_inode();
+
+            for name in names.iter() {
+                root.create(name, InodeType::File, InodeMode::all())
+                    .unwrap();
+            }
+            let too_long = "x".repeat(MAX_NAME_LEN + 1);
+            assert_eq!(
+                root.create(&too_long, InodeType::File, InodeMode::all())
+                    .unwrap_err()
+                    .error(),
+                Errno::ENAMETOOLONG
+            );
+            assert_eq!(list_names(&root), names);
+
+            // The names, including their cases, survive a remount.
+            let fs = remount(fs, &disk);
+            let root = fs.root_inode();
+            assert_eq!(list_names(&root), names);
+            for name in names.iter() {
+                let inode = root.lookup(name).unwrap();
+                let upper = root.lookup(&name.to_uppercase()).unwrap();
+                assert_eq!(inode.ino(), upper.ino());
+            }
+
+            for name in names.iter() {
+                root.unlink(name).unwrap();
+            }
+            assert!(list_names(&root).is_empty());
+        }
+    }
+
+    #[ktest]
+    fn free_cluster_accounting() {
+        for fat_type in FAT_TYPES {
+            let disk = format(fat_type);
+            let fs = mount(&disk);
+            let cluster_size = fs.super_block().cluster_size;
+            let root = fs.root_inode();
+
+            // An empty file has no clusters.
+            let nr_free = fs.fat().free_count();
+            let file = root
+                .create("a.bin", InodeType::File, InodeMode::all())
+                .unwrap();
+            assert_eq!(fs.fat().free_count(), nr_free);
+
+            file.write_bytes_at(0, &vec![0xAA; cluster_size * 5 + 1])
+                .unwrap();
+            assert_eq!(fs.fat().free_count(), nr_free - 6);
+            file.resize(cluster_size).unwrap();
+            assert_eq!(fs.fat().free_count(), nr_free - 1);
+
+            // A directory has a cluster from its creation.
+            root.create("dir", InodeType::Dir, InodeMode::all())
+                .unwrap();
+            assert_eq!(fs.fat().free_count(), nr_free - 2);
+
+            // The count is kept in the FSInfo of FAT32 and recounted for the others.
+            let fs = remount(fs, &disk);
+            assert_eq!(fs.fat().free_count(), nr_free - 2);
+
+            let root = fs.root_inode();
+            root.unlink("a.bin").unwrap();
+            root.rmdir("dir").unwrap();
+            assert_eq!(fs.fat().free_count(), nr_free);
+
+            let fs = remount(fs, &disk);
+            assert_eq!(fs.fat().free_count(), nr_free);
+        }
+    }
+}
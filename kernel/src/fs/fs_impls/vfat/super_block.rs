@@ -0,0 +1,279 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_block::{BlockDevice, SECTOR_SIZE};
+use ostd::{const_assert, mm::VmIo};
+
+use super::{
+    dentry::DENTRY_SIZE,
+    fat::{ClusterId, FIRST_CLUSTER},
+};
+use crate::prelude::*;
+
+/// The magic number reported for FAT filesystems, i.e., `MSDOS_SUPER_MAGIC`.
+pub(super) const VFAT_MAGIC: u64 = 0x4d44;
+
+const BOOT_SIGNATURE: u16 = 0xAA55;
+
+const FS_INFO_LEAD_SIGNATURE: u32 = 0x4161_5252;
+const FS_INFO_STRUCT_SIGNATURE: u32 = 0x6141_7272;
+const FS_INFO_TRAIL_SIGNATURE: u32 = 0xAA55_0000;
+
+/// The value of the FSInfo fields that are unknown.
+pub(super) const FS_INFO_UNKNOWN: u32 = 0xFFFF_FFFF;
+
+/// The maximum numbers of clusters of FAT12 and FAT16.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.16/source/fs/fat/fat.h#L27>
+const MAX_FAT12_CLUSTERS: u32 = 4084;
+const MAX_FAT16_CLUSTERS: u32 = 65524;
+const MAX_FAT32_CLUSTERS: u32 = 0x0FFF_FFF6 - FIRST_CLUSTER + 1;
+
+/// The variant of a FAT filesystem, which is given by its number of clusters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum FatType {
+    Fat12,
+    Fat16,
+    Fat32,
+}
+
+/// The boot sector, which contains the BIOS parameter block (BPB).
+///
+/// The fields starting from `fat_size_32` are only valid for FAT32.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Pod)]
+struct RawBootSector {
+    jump_boot: [u8; 3],
+    oem_name: [u8; 8],
+    bytes_per_sector: u16,
+    sectors_per_cluster: u8,
+    reserved_sectors: u16,
+    num_fats: u8,
+    root_entries: u16,
+    total_sectors_16: u16,
+    media: u8,
+    fat_size_16: u16,
+    sectors_per_track: u16,
+    num_heads: u16,
+    hidden_sectors: u32,
+    total_sectors_32: u32,
+    fat_size_32: u32,
+    ext_flags: u16,
+    fs_version: u16,
+    root_cluster: u32,
+    fs_info_sector: u16,
+    backup_boot_sector: u16,
+    reserved: [u8; 12],
+    drive_number: u8,
+    reserved1: u8,
+    boot_signature: u8,
+    volume_id: u32,
+    volume_label: [u8; 11],
+    fs_type: [u8; 8],
+    boot_code: [u8; 420],
+    signature: u16,
+}
+
+const_assert!(size_of::<RawBootSector>() == SECTOR_SIZE);
+
+/// The FSInfo sector of FAT32, which caches the allocation hints.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct RawFsInfo {
+    lead_signature: u32,
+    reserved: [u8; 480],
+    struct_signature: u32,
+    /// The last known number of free clusters.
+    pub(super) free_count: u32,
+    /// The cluster from which to start looking for free clusters.
+    pub(super) next_free: u32,
+    reserved1: [u8; 12],
+    trail_signature: u32,
+}
+
+const_assert!(size_of::<RawFsInfo>() == SECTOR_SIZE);
+
+impl RawFsInfo {
+    pub(super) fn is_valid(&self) -> bool {
+        self.lead_signature == FS_INFO_LEAD_SIGNATURE
+            && self.struct_signature == FS_INFO_STRUCT_SIGNATURE
+            && self.trail_signature == FS_INFO_TRAIL_SIGNATURE
+    }
+}
+
+/// The geometry of a FAT filesystem, which is parsed from the boot sector.
+///
+/// All the offsets and the sizes are in bytes.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct VfatSuperBlock {
+    pub(super) fat_type: FatType,
+    pub(super) sector_size: usize,
+    pub(super) cluster_size: usize,
+    /// The offset of the first FAT.
+    pub(super) fat_offset: usize,
+    /// The size of each FAT.
+    pub(super) fat_size: usize,
+    pub(super) num_fats: usize,
+    /// The only FAT in use if mirroring is disabled, which is only possible on FAT32.
+    pub(super) active_fat: Option<usize>,
+    /// The offset of the fixed root directory of FAT12 and FAT16.
+    pub(super) root_dir_offset: usize,
+    /// The size of the fixed root directory of FAT12 and FAT16.
+    pub(super) root_dir_size: usize,
+    /// The first cluster of the root directory of FAT32.
+    pub(super) root_cluster: ClusterId,
+    /// The offset of the first data cluster.
+    pub(super) data_offset: usize,
+    pub(super) num_clusters: u32,
+    /// The offset of the FSInfo sector of FAT32.
+    pub(super) fs_info_offset: Option<usize>,
+    pub(super) volume_id: u32,
+}
+
+impl VfatSuperBlock {
+    /// Reads and validates the boot sector.
+    pub(super) fn read(block_device: &dyn BlockDevice) -> Result<Self> {
+        let boot_sector = block_device.read_val::<RawBootSector>(0)?;
+        if boot_sector.signature != BOOT_SIGNATURE {
+            return_errno_with_message!(Errno::EINVAL, "invalid boot sector signature");
+        }
+
+        let sector_size = boot_sector.bytes_per_sector as usize;
+        if !sector_size.is_power_of_two() || !(SECTOR_SIZE..=PAGE_SIZE).contains(&sector_size) {
+            return_errno_with_message!(Errno::EINVAL, "invalid sector size");
+        }
+        let sectors_per_cluster = boot_sector.sectors_per_cluster as usize;
+        if !sectors_per_cluster.is_power_of_two() {
+            return_errno_with_message!(Errno::EINVAL, "invalid sectors per cluster");
+        }
+        let cluster_size = sector_size * sectors_per_cluster;
+        if boot_sector.reserved_sectors == 0 || boot_sector.num_fats == 0 {
+            return_errno_with_message!(Errno::EINVAL, "invalid reserved sectors or FATs");
+        }
+        if boot_sector.media != 0xF0 && boot_sector.media < 0xF8 {
+            return_errno_with_message!(Errno::EINVAL, "invalid media descriptor");
+        }
+
+        let fat_sectors = if boot_sector.fat_size_16 != 0 {
+            boot_sector.fat_size_16 as usize
+        } else {
+            boot_sector.fat_size_32 as usize
+        };
+        let total_sectors = if boot_sector.total_sectors_16 != 0 {
+            boot_sector.total_sectors_16 as usize
+        } else {
+            boot_sector.total_sectors_32 as usize
+        };
+        let num_fats = boot_sector.num_fats as usize;
+        let root_dir_sectors =
+            (boot_sector.root_entries as usize * DENTRY_SIZE).div_ceil(sector_size);
+
+        let fat_offset = boot_sector.reserved_sectors as usize * sector_size;
+        let fat_size = fat_sectors * sector_size;
+        let root_dir_offset = fat_offset + num_fats * fat_size;
+        let root_dir_size = root_dir_sectors * sector_size;
+        let data_offset = root_dir_offset + root_dir_size;
+        if fat_size == 0 || total_sectors * sector_size <= data_offset {
+            return_errno_with_message!(Errno::EINVAL, "invalid filesystem layout");
+        }
+        if data_offset + cluster_size > block_device.metadata().nr_sectors * SECTOR_SIZE {
+            return_errno_with_message!(Errno::EINVAL, "the device is too small");
+        }
+
+        let num_clusters = ((total_sectors * sector_size - data_offset) / cluster_size)
+            .try_into()
+            .unwrap_or(u32::MAX);
+        let fat_type = if num_clusters <= MAX_FAT12_CLUSTERS {
+            FatType::Fat12
+        } else if num_clusters <= MAX_FAT16_CLUSTERS {
+            FatType::Fat16
+        } else {
+            FatType::Fat32
+        };
+        if (fat_type == FatType::Fat32) != (boot_sector.root_entries == 0) {
+            return_errno_with_message!(Errno::EINVAL, "invalid root directory entries");
+        }
+
+        // The clusters that cannot be described by the FAT are unusable.
+        let fat_entries = match fat_type {
+            FatType::Fat12 => fat_size * 2 / 3,
+            FatType::Fat16 => fat_size / 2,
+            FatType::Fat32 => fat_size / 4,
+        };
+        let num_clusters = num_clusters.min(
+            fat_entries
+                .saturating_sub(FIRST_CLUSTER as usize)
+                .min(MAX_FAT32_CLUSTERS as usize) as u32,
+        );
+        if num_clusters == 0 {
+            return_errno_with_message!(Errno::EINVAL, "no data clusters");
+        }
+
+        let mut super_block = Self {
+            fat_type,
+            sector_size,
+            cluster_size,
+            fat_offset,
+            fat_size,
+            num_fats,
+            active_fat: None,
+            root_dir_offset,
+            root_dir_size,
+            root_cluster: 0,
+            data_offset,
+            num_clusters,
+            fs_info_offset: None,
+            volume_id: 0,
+        };
+
+        if fat_type != FatType::Fat32 {
+            // The extended BPB of FAT12 and FAT16 starts right after the common BPB.
+            let bytes = boot_sector.as_bytes();
+            if bytes[38] == 0x29 {
+                super_block.volume_id = u32::from_le_bytes(bytes[39..43].try_into().unwrap());
+            }
+            return Ok(super_block);
+        }
+
+        if boot_sector.fs_version != 0 {
+            return_errno_with_message!(Errno::EINVAL, "unsupported FAT32 version");
+        }
+        if !super_block.is_valid_cluster(boot_sector.root_cluster) {
+            return_errno_with_message!(Errno::EINVAL, "invalid root cluster");
+        }
+        super_block.root_cluster = boot_sector.root_cluster;
+        // Bit 7 of the extended flags disables mirroring, and the low bits give the active FAT.
+        if boot_sector.ext_flags & 0x80 != 0 {
+            let active_fat = (boot_sector.ext_flags & 0xF) as usize;
+            if active_fat >= num_fats {
+                return_errno_with_message!(Errno::EINVAL, "invalid active FAT");
+            }
+            super_block.active_fat = Some(active_fat);
+        }
+        let fs_info_sector = boot_sector.fs_info_sector as usize;
+        if fs_info_sector != 0 && fs_info_sector < boot_sector.reserved_sectors as usize {
+            super_block.fs_info_offset = Some(fs_info_sector * sector_size);
+        }
+        if boot_sector.boot_signature == 0x29 {
+            super_block.volume_id = boot_sector.volume_id;
+        }
+
+        Ok(super_block)
+    }
+
+    /// Returns whether the cluster is a data cluster of the filesystem.
+    pub(super) fn is_valid_cluster(&self, cluster: ClusterId) -> bool {
+        (FIRST_CLUSTER..FIRST_CLUSTER + self.num_clusters).contains(&cluster)
+    }
+
+    /// Returns the device offset of a data cluster.
+    pub(super) fn cluster_offset(&self, cluster: ClusterId) -> usize {
+        debug_assert!(self.is_valid_cluster(cluster));
+        self.data_offset + (cluster - FIRST_CLUSTER) as usize * self.cluster_size
+    }
+
+    /// Returns the end of the allocation tables, from which the directories and the
+    /// files are stored.
+    pub(super) fn fat_end(&self) -> usize {
+        self.root_dir_offset
+    }
+}
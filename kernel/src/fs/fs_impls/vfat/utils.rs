@@ -0,0 +1,78 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time};
+
+/// The earliest time that can be represented, i.e., 1980-01-01 00:00:00 UTC.
+const DOS_MIN_SECS: u64 = 315_532_800;
+/// The latest time that can be represented, i.e., 2107-12-31 23:59:58 UTC.
+const DOS_MAX_SECS: u64 = 4_354_819_198;
+
+/// A timestamp in the DOS format.
+///
+/// The date and the time have a resolution of two seconds, and only the creation
+/// time has an additional field in units of 10 milliseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(super) struct DosTime {
+    /// The year since 1980 (bits 9-15), the month (bits 5-8) and the day (bits 0-4).
+    pub(super) date: u16,
+    /// The hour (bits 11-15), the minute (bits 5-10) and the second divided by two (bits 0-4).
+    pub(super) time: u16,
+    /// The remainder in units of 10 milliseconds, which ranges from 0 to 199.
+    pub(super) centis: u8,
+}
+
+impl DosTime {
+    /// Converts a time since the Unix epoch, clamping it to the range of DOS timestamps.
+    pub(super) fn from_duration(duration: Duration) -> Self {
+        let secs = duration.as_secs();
+        let (secs, nanos) = if secs < DOS_MIN_SECS {
+            (DOS_MIN_SECS, 0)
+        } else if secs > DOS_MAX_SECS {
+            (DOS_MAX_SECS, 0)
+        } else {
+            (secs, duration.subsec_nanos())
+        };
+
+        // The clamped time is always representable.
+        let date_time = OffsetDateTime::from_unix_timestamp(secs as i64).unwrap();
+        let date = (((date_time.year() - 1980) as u16) << 9)
+            | ((date_time.month() as u16) << 5)
+            | date_time.day() as u16;
+        let time = ((date_time.hour() as u16) << 11)
+            | ((date_time.minute() as u16) << 5)
+            | (date_time.second() as u16 >> 1);
+        let centis = ((date_time.second() % 2) as u32 * 100 + nanos / 10_000_000) as u8;
+
+        Self { date, time, centis }
+    }
+
+    /// Converts to a time since the Unix epoch.
+    ///
+    /// Invalid fields are clamped to the nearest valid values.
+    pub(super) fn as_duration(&self) -> Duration {
+        let year = 1980 + (self.date >> 9) as i32;
+        let month = Month::try_from(((self.date >> 5) & 0xF).clamp(1, 12) as u8).unwrap();
+        let day = (self.date & 0x1F).max(1) as u8;
+        let date = (1..=day)
+            .rev()
+            .find_map(|day| Date::from_calendar_date(year, month, day).ok())
+            .unwrap();
+
+        let hour = ((self.time >> 11) as u8).min(23);
+        let minute = (((self.time >> 5) & 0x3F) as u8).min(59);
+        let second = (((self.time & 0x1F) * 2) as u8).min(58);
+        let time = Time::from_hms(hour, minute, second).unwrap();
+
+        let secs = PrimitiveDateTime::new(date, time)
+            .assume_utc()
+            .unix_timestamp() as u64;
+        let centis = self.centis.min(199) as u64;
+        Duration::from_secs(secs) + Duration::from_millis(centis * 10)
+    }
+}
+
+pub(super) fn now() -> Duration {
+    crate::time::clocks::RealTimeCoarseClock::get().read_time()
+}
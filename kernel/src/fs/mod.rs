@@ -9,7 +9,8 @@ pub mod utils;
 pub mod vfs;
 
 pub use fs_impls::{
-    cgroupfs, configfs, devpts, exfat, ext2, procfs, pseudofs, ramfs, sysfs, tmpfs,
+    cgroupfs, configfs, debugfs, devpts, devtmpfs, exfat, ext2, fuse, iso9660, procfs, pseudofs,
+    ramfs, securityfs, squashfs, sysfs, tmpfs, tracefs, vfat,
 };
 
 use crate::{
@@ -6,7 +6,10 @@ use atomic_integer_wrapper::define_atomic_version_of_integer_like_type;
 use device_id::DeviceId;
 
 use super::inode::Inode;
-use crate::prelude::*;
+use crate::{
+    fs::vfs::{fscrypt::FscryptKeyring, quota::DiskQuotas},
+    prelude::*,
+};
 
 /// Common interface implemented by each concrete file system instance.
 pub trait FileSystem: Any + Sync + Send {
@@ -41,6 +44,28 @@ pub trait FileSystem: Any + Sync + Send {
         Ok(())
     }
 
+    /// Returns the disk quotas of this file system.
+    ///
+    /// Returns `None` if the file system does not support disk quotas.
+    fn disk_quotas(&self) -> Option<&DiskQuotas> {
+        None
+    }
+
+    /// Returns the keyring that holds the master keys for file encryption.
+    ///
+    /// Returns `None` if the file system does not support file encryption.
+    fn fscrypt_keyring(&self) -> Option<&FscryptKeyring> {
+        None
+    }
+
+    /// Discards the free blocks in the byte range of `start..start + len` on the device.
+    ///
+    /// The free extents shorter than `min_len` bytes are skipped. Returns the number of bytes
+    /// discarded.
+    fn trim(&self, _start: u64, _len: u64, _min_len: u64) -> Result<u64> {
+        return_errno_with_message!(Errno::ENOTTY, "the file system does not support trimming");
+    }
+
     /// Returns the FS event subscriber stats of this file system.
     fn fs_event_subscriber_stats(&self) -> &FsEventSubscriberStats;
 }
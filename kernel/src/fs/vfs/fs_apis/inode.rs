@@ -12,6 +12,7 @@ use spin::Once;
 
 use super::{
     file_system::FileSystem,
+    posix_acl::{PosixAcl, PosixAclType},
     xattr::{XattrName, XattrNamespace, XattrSetFlags},
 };
 use crate::{
@@ -19,7 +20,10 @@ use crate::{
     fs::{
         file::{AccessMode, FileIo, InodeMode, InodeType, Permission, StatusFlags},
         utils::DirentVisitor,
-        vfs::path::Path,
+        vfs::{
+            fscrypt::{FscryptContext, FscryptPolicy},
+            path::Path,
+        },
     },
     prelude::*,
     process::{Gid, Uid, credentials::capabilities::CapSet, posix_thread::AsPosixThread},
@@ -231,6 +235,14 @@ impl MknodType {
             MknodType::BlockDevice(_) => Some(DeviceType::Block),
         }
     }
+
+    pub fn inode_type(&self) -> InodeType {
+        match self {
+            MknodType::NamedPipe => InodeType::NamedPipe,
+            MknodType::CharDevice(_) => InodeType::CharDevice,
+            MknodType::BlockDevice(_) => InodeType::BlockDevice,
+        }
+    }
 }
 
 /// I/O operations in an [`Inode`].
@@ -419,71 +431,117 @@ pub trait Inode: Any + InodeIo + Send + Sync {
         Err(Error::new(Errno::EOPNOTSUPP))
     }
 
+    /// Returns the encryption context of this inode.
+    ///
+    /// Returns `None` if the inode is not encrypted,
+    /// or if the file system does not support file encryption.
+    fn fscrypt_context(&self) -> Option<FscryptContext> {
+        None
+    }
+
+    /// Sets the encryption policy of this inode, which must be an empty directory.
+    fn set_fscrypt_policy(&self, policy: &FscryptPolicy) -> Result<()> {
+        Err(Error::new(Errno::EOPNOTSUPP))
+    }
+
+    /// Returns the POSIX ACL of the given type.
+    ///
+    /// Returns `None` if the inode has no such ACL,
+    /// or if the file system does not support POSIX ACLs.
+    fn posix_acl(&self, type_: PosixAclType) -> Result<Option<PosixAcl>> {
+        Ok(None)
+    }
+
     /// Used to check for read/write/execute permissions on a file.
     ///
     /// Similar to Linux, using "fsuid" here allows setting filesystem permissions
     /// without changing the "normal" uids for other tasks.
-    fn check_permission(&self, mut perm: Permission) -> Result<()> {
-        let creds = match Task::current() {
-            Some(task) => match task.as_posix_thread() {
-                Some(thread) => thread.credentials(),
-                None => return Ok(()),
-            },
+    fn check_permission(&self, perm: Permission) -> Result<()> {
+        let metadata = self.metadata();
+        let acl = self.posix_acl(PosixAclType::Access)?;
+        check_dac_permission(
+            metadata.mode,
+            metadata.uid,
+            metadata.gid,
+            acl.as_ref(),
+            perm,
+        )
+    }
+}
+
+/// Checks the discretionary access control (DAC) permissions of the current thread
+/// against a file with the given mode and owners.
+///
+/// The owners are the ones seen by the current thread. They may differ from the
+/// ones stored in the file system (e.g., for files accessed via idmapped mounts).
+/// If the file has an access ACL, the ACL is checked instead of the permission bits.
+pub(in crate::fs) fn check_dac_permission(
+    mode: InodeMode,
+    uid: Uid,
+    gid: Gid,
+    acl: Option<&PosixAcl>,
+    mut perm: Permission,
+) -> Result<()> {
+    let creds = match Task::current() {
+        Some(task) => match task.as_posix_thread() {
+            Some(thread) => thread.credentials(),
             None => return Ok(()),
-        };
-
-        // With DAC_OVERRIDE capability, the user can bypass some permission checks.
-        if creds.effective_capset().contains(CapSet::DAC_OVERRIDE) {
-            // Read/write DACs are always overridable.
-            perm -= Permission::MAY_READ | Permission::MAY_WRITE;
-
-            // Executable DACs are overridable when there is at least one exec bit set.
-            if perm.may_exec() {
-                let metadata = self.metadata();
-                let mode = metadata.mode;
-
-                if mode.is_owner_executable()
-                    || mode.is_group_executable()
-                    || mode.is_other_executable()
-                {
-                    perm -= Permission::MAY_EXEC;
-                } else {
-                    return_errno_with_message!(
-                        Errno::EACCES,
-                        "root execute permission denied: no execute bits set"
-                    );
-                }
+        },
+        None => return Ok(()),
+    };
+
+    // With DAC_OVERRIDE capability, the user can bypass some permission checks.
+    if creds.effective_capset().contains(CapSet::DAC_OVERRIDE) {
+        // Read/write DACs are always overridable.
+        perm -= Permission::MAY_READ | Permission::MAY_WRITE;
+
+        // Executable DACs are overridable when there is at least one exec bit set.
+        if perm.may_exec() {
+            if mode.is_owner_executable()
+                || mode.is_group_executable()
+                || mode.is_other_executable()
+            {
+                perm -= Permission::MAY_EXEC;
+            } else {
+                return_errno_with_message!(
+                    Errno::EACCES,
+                    "root execute permission denied: no execute bits set"
+                );
             }
         }
+    }
 
-        perm =
-            perm.intersection(Permission::MAY_READ | Permission::MAY_WRITE | Permission::MAY_EXEC);
-        let metadata = self.metadata();
-        let mode = metadata.mode;
+    perm = perm.intersection(Permission::MAY_READ | Permission::MAY_WRITE | Permission::MAY_EXEC);
 
-        if metadata.uid == creds.fsuid() {
-            if (perm.may_read() && !mode.is_owner_readable())
-                || (perm.may_write() && !mode.is_owner_writable())
-                || (perm.may_exec() && !mode.is_owner_executable())
-            {
-                return_errno_with_message!(Errno::EACCES, "owner permission check failed");
-            }
-        } else if metadata.gid == creds.fsgid() {
-            if (perm.may_read() && !mode.is_group_readable())
-                || (perm.may_write() && !mode.is_group_writable())
-                || (perm.may_exec() && !mode.is_group_executable())
-            {
-                return_errno_with_message!(Errno::EACCES, "group permission check failed");
-            }
-        } else if (perm.may_read() && !mode.is_other_readable())
-            || (perm.may_write() && !mode.is_other_writable())
-            || (perm.may_exec() && !mode.is_other_executable())
+    if let Some(acl) = acl {
+        let fsgid = creds.fsgid();
+        let groups = creds.groups();
+        let in_group = |group: Gid| group == fsgid || groups.contains(&group);
+        return acl.check_permission(uid, gid, creds.fsuid(), in_group, perm);
+    }
+
+    if uid == creds.fsuid() {
+        if (perm.may_read() && !mode.is_owner_readable())
+            || (perm.may_write() && !mode.is_owner_writable())
+            || (perm.may_exec() && !mode.is_owner_executable())
         {
-            return_errno_with_message!(Errno::EACCES, "other permission check failed");
+            return_errno_with_message!(Errno::EACCES, "owner permission check failed");
         }
-
-        Ok(())
+    } else if gid == creds.fsgid() {
+        if (perm.may_read() && !mode.is_group_readable())
+            || (perm.may_write() && !mode.is_group_writable())
+            || (perm.may_exec() && !mode.is_group_executable())
+        {
+            return_errno_with_message!(Errno::EACCES, "group permission check failed");
+        }
+    } else if (perm.may_read() && !mode.is_other_readable())
+        || (perm.may_write() && !mode.is_other_writable())
+        || (perm.may_exec() && !mode.is_other_executable())
+    {
+        return_errno_with_message!(Errno::EACCES, "other permission check failed");
     }
+
+    Ok(())
 }
 
 impl dyn Inode {
@@ -503,7 +561,6 @@ impl dyn Inode {
         self.read_at(offset, &mut writer, StatusFlags::empty())
     }
 
-    #[cfg_attr(not(ktest), expect(dead_code))]
     pub fn write_bytes_at(&self, offset: usize, buf: &[u8]) -> Result<usize> {
         let mut reader = VmReader::from(buf).to_fallible();
         self.write_at(offset, &mut reader, StatusFlags::empty())
@@ -3,7 +3,7 @@
 use alloc::boxed::ThinBox;
 
 use crate::fs::{
-    file::flock::FlockList,
+    file::{flock::FlockList, lease::LeaseList},
     vfs::{inode::Inode, notify::FsEventPublisher, range_lock::RangeLockList},
 };
 
@@ -11,6 +11,7 @@ use crate::fs::{
 pub struct FsLockContext {
     range_lock_list: RangeLockList,
     flock_list: FlockList,
+    lease_list: LeaseList,
 }
 
 impl FsLockContext {
@@ -18,6 +19,7 @@ impl FsLockContext {
         Self {
             range_lock_list: RangeLockList::new(),
             flock_list: FlockList::new(),
+            lease_list: LeaseList::new(),
         }
     }
 
@@ -30,6 +32,11 @@ impl FsLockContext {
     pub fn flock_list(&self) -> &FlockList {
         &self.flock_list
     }
+
+    /// Returns a reference to the lease list.
+    pub fn lease_list(&self) -> &LeaseList {
+        &self.lease_list
+    }
 }
 
 /// A trait that instantiates kernel types for the inode [`Extension`].
@@ -7,6 +7,7 @@
 pub mod file_system;
 pub mod inode;
 pub mod inode_ext;
+pub mod posix_acl;
 pub mod registry;
 pub mod xattr;
 
@@ -0,0 +1,350 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! POSIX access control lists (ACLs).
+//!
+//! A POSIX ACL extends the permission bits of a file with entries for
+//! additional users and groups. The ACLs are exchanged with user space
+//! as the values of the `system.posix_acl_access` and `system.posix_acl_default`
+//! extended attributes.
+//!
+//! Reference: <https://elixir.bootlin.com/linux/v6.17/source/fs/posix_acl.c>
+
+use super::xattr::XattrName;
+use crate::{
+    fs::file::{InodeMode, Permission},
+    prelude::*,
+    process::{Gid, Uid},
+};
+
+pub const XATTR_NAME_POSIX_ACL_ACCESS: &str = "system.posix_acl_access";
+pub const XATTR_NAME_POSIX_ACL_DEFAULT: &str = "system.posix_acl_default";
+
+/// The version of the xattr representation of POSIX ACLs.
+const POSIX_ACL_XATTR_VERSION: u32 = 0x0002;
+/// The ID of the entries that are not associated with a user or a group.
+const ACL_UNDEFINED_ID: u32 = u32::MAX;
+
+/// The type of a POSIX ACL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PosixAclType {
+    /// The ACL that is checked when the file is accessed.
+    Access,
+    /// The ACL that is inherited by the files created in a directory.
+    Default,
+}
+
+impl PosixAclType {
+    /// Returns the type of the ACL that is stored in the xattr named `name`,
+    /// or `None` if the xattr is not an ACL.
+    pub fn from_xattr_name(name: &XattrName) -> Option<Self> {
+        match name.full_name() {
+            XATTR_NAME_POSIX_ACL_ACCESS => Some(Self::Access),
+            XATTR_NAME_POSIX_ACL_DEFAULT => Some(Self::Default),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the xattr that stores the ACL.
+    pub fn xattr_name(&self) -> XattrName<'static> {
+        let full_name = match self {
+            Self::Access => XATTR_NAME_POSIX_ACL_ACCESS,
+            Self::Default => XATTR_NAME_POSIX_ACL_DEFAULT,
+        };
+        XattrName::try_from_full_name(full_name).unwrap()
+    }
+}
+
+/// The tag of a POSIX ACL entry.
+///
+/// The entries of a valid ACL are sorted by their tags in the order below.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, TryFromInt)]
+#[repr(u16)]
+pub enum PosixAclTag {
+    /// The owner of the file.
+    UserObj = 0x01,
+    /// A user specified by the ID.
+    User = 0x02,
+    /// The owning group of the file.
+    GroupObj = 0x04,
+    /// A group specified by the ID.
+    Group = 0x08,
+    /// The maximum permissions granted to `User`, `GroupObj` and `Group` entries.
+    Mask = 0x10,
+    /// The other users.
+    Other = 0x20,
+}
+
+/// An entry of a POSIX ACL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PosixAclEntry {
+    tag: PosixAclTag,
+    /// The permission bits, where 4 is for read, 2 is for write and 1 is for execute.
+    perm: u16,
+    /// The user or group ID for `User` and `Group` entries.
+    id: u32,
+}
+
+/// The xattr representation of a POSIX ACL entry.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct RawPosixAclEntry {
+    tag: u16,
+    perm: u16,
+    id: u32,
+}
+
+/// A POSIX ACL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PosixAcl {
+    entries: Vec<PosixAclEntry>,
+}
+
+impl PosixAcl {
+    /// Parses an ACL from its xattr representation.
+    ///
+    /// Returns `None` if the representation contains no entries,
+    /// which means that the ACL should be removed.
+    pub fn from_xattr_value(value: &[u8]) -> Result<Option<Self>> {
+        const HEADER_SIZE: usize = size_of::<u32>();
+        const ENTRY_SIZE: usize = size_of::<RawPosixAclEntry>();
+
+        if value.is_empty() {
+            return Ok(None);
+        }
+        if value.len() < HEADER_SIZE || (value.len() - HEADER_SIZE) % ENTRY_SIZE != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the ACL xattr has an invalid size");
+        }
+        if u32::from_le_bytes(value[..HEADER_SIZE].try_into().unwrap()) != POSIX_ACL_XATTR_VERSION {
+            return_errno_with_message!(Errno::EOPNOTSUPP, "the ACL xattr version is unsupported");
+        }
+
+        let mut entries = Vec::new();
+        for raw_entry in value[HEADER_SIZE..].chunks_exact(ENTRY_SIZE) {
+            let raw_entry = RawPosixAclEntry::from_bytes(raw_entry);
+            let tag = PosixAclTag::try_from(u16::from_le(raw_entry.tag))
+                .map_err(|_| Error::with_message(Errno::EINVAL, "invalid ACL entry tag"))?;
+            let id = match tag {
+                PosixAclTag::User | PosixAclTag::Group => u32::from_le(raw_entry.id),
+                _ => ACL_UNDEFINED_ID,
+            };
+            entries.push(PosixAclEntry {
+                tag,
+                perm: u16::from_le(raw_entry.perm),
+                id,
+            });
+        }
+        if entries.is_empty() {
+            return Ok(None);
+        }
+
+        let acl = Self { entries };
+        acl.validate()?;
+        Ok(Some(acl))
+    }
+
+    /// Returns the xattr representation of the ACL.
+    pub fn to_xattr_value(&self) -> Vec<u8> {
+        let mut value = Vec::with_capacity(
+            size_of::<u32>() + self.entries.len() * size_of::<RawPosixAclEntry>(),
+        );
+        value.extend_from_slice(&POSIX_ACL_XATTR_VERSION.to_le_bytes());
+        for entry in self.entries.iter() {
+            let raw_entry = RawPosixAclEntry {
+                tag: (entry.tag as u16).to_le(),
+                perm: entry.perm.to_le(),
+                id: entry.id.to_le(),
+            };
+            value.extend_from_slice(raw_entry.as_bytes());
+        }
+        value
+    }
+
+    /// Checks that the entries are well-formed.
+    ///
+    /// The entries must be sorted by their tags and IDs. There must be exactly one
+    /// `UserObj`, `GroupObj` and `Other` entry, and there must be a `Mask` entry if
+    /// there are `User` or `Group` entries.
+    fn validate(&self) -> Result<()> {
+        let mut prev: Option<&PosixAclEntry> = None;
+        let mut has_named_entries = false;
+        for entry in self.entries.iter() {
+            if entry.perm & !0o7 != 0 {
+                return_errno_with_message!(Errno::EINVAL, "invalid ACL entry permissions");
+            }
+            if let Some(prev) = prev {
+                let is_ordered = prev.tag < entry.tag
+                    || (prev.tag == entry.tag
+                        && matches!(entry.tag, PosixAclTag::User | PosixAclTag::Group)
+                        && prev.id < entry.id);
+                if !is_ordered {
+                    return_errno_with_message!(Errno::EINVAL, "the ACL entries are not sorted");
+                }
+            }
+            if matches!(entry.tag, PosixAclTag::User | PosixAclTag::Group) {
+                has_named_entries = true;
+            }
+            prev = Some(entry);
+        }
+
+        let has_tag = |tag| self.entries.iter().any(|entry| entry.tag == tag);
+        if !has_tag(PosixAclTag::UserObj)
+            || !has_tag(PosixAclTag::GroupObj)
+            || !has_tag(PosixAclTag::Other)
+            || (has_named_entries && !has_tag(PosixAclTag::Mask))
+        {
+            return_errno_with_message!(Errno::EINVAL, "the ACL misses required entries");
+        }
+        Ok(())
+    }
+
+    /// Returns whether the ACL can be fully represented by the permission bits.
+    pub fn is_minimal(&self) -> bool {
+        self.entries.iter().all(|entry| {
+            matches!(
+                entry.tag,
+                PosixAclTag::UserObj | PosixAclTag::GroupObj | PosixAclTag::Other
+            )
+        })
+    }
+
+    /// Returns the mode whose permission bits are set according to the ACL.
+    ///
+    /// The group permission bits reflect the `Mask` entry if there is one.
+    pub fn equiv_mode(&self, mode: InodeMode) -> InodeMode {
+        let mut bits = mode.bits() & !0o777;
+        let has_mask = self.has_mask();
+        for entry in self.entries.iter() {
+            match entry.tag {
+                PosixAclTag::UserObj => bits |= entry.perm << 6,
+                PosixAclTag::GroupObj if !has_mask => bits |= entry.perm << 3,
+                PosixAclTag::Mask => bits |= entry.perm << 3,
+                PosixAclTag::Other => bits |= entry.perm,
+                _ => {}
+            }
+        }
+        InodeMode::from_bits_truncate(bits)
+    }
+
+    /// Updates the ACL after the mode of the file is changed.
+    pub fn chmod(&mut self, mode: InodeMode) {
+        let bits = mode.bits();
+        let has_mask = self.has_mask();
+        for entry in self.entries.iter_mut() {
+            match entry.tag {
+                PosixAclTag::UserObj => entry.perm = (bits >> 6) & 0o7,
+                PosixAclTag::GroupObj if !has_mask => entry.perm = (bits >> 3) & 0o7,
+                PosixAclTag::Mask => entry.perm = (bits >> 3) & 0o7,
+                PosixAclTag::Other => entry.perm = bits & 0o7,
+                _ => {}
+            }
+        }
+    }
+
+    /// Turns the default ACL of a directory into the access ACL of a new file
+    /// created with `mode` in the directory.
+    ///
+    /// Both the ACL and the mode are restricted by each other,
+    /// and the restricted mode is returned.
+    pub fn create_masq(&mut self, mode: InodeMode) -> InodeMode {
+        let mut bits = mode.bits();
+        let has_mask = self.has_mask();
+        for entry in self.entries.iter_mut() {
+            let shift = match entry.tag {
+                PosixAclTag::UserObj => 6,
+                PosixAclTag::GroupObj if !has_mask => 3,
+                PosixAclTag::Mask => 3,
+                PosixAclTag::Other => 0,
+                _ => continue,
+            };
+            entry.perm &= (bits >> shift) & 0o7;
+            bits &= (entry.perm << shift) | !(0o7 << shift);
+        }
+        InodeMode::from_bits_truncate(bits)
+    }
+
+    /// Maps the user and group IDs in the entries.
+    pub fn map_ids(&mut self, map_uid: impl Fn(Uid) -> Uid, map_gid: impl Fn(Gid) -> Gid) {
+        for entry in self.entries.iter_mut() {
+            match entry.tag {
+                PosixAclTag::User => entry.id = map_uid(Uid::new(entry.id)).into(),
+                PosixAclTag::Group => entry.id = map_gid(Gid::new(entry.id)).into(),
+                _ => {}
+            }
+        }
+    }
+
+    /// Checks whether a thread with `fsuid` can access the file owned by `uid` and `gid`
+    /// with `perm` permissions.
+    ///
+    /// The `in_group` closure tells whether the thread belongs to a group.
+    pub(in crate::fs) fn check_permission(
+        &self,
+        uid: Uid,
+        gid: Gid,
+        fsuid: Uid,
+        in_group: impl Fn(Gid) -> bool,
+        perm: Permission,
+    ) -> Result<()> {
+        let mut want = 0;
+        if perm.may_read() {
+            want |= 0o4;
+        }
+        if perm.may_write() {
+            want |= 0o2;
+        }
+        if perm.may_exec() {
+            want |= 0o1;
+        }
+
+        let mask = self
+            .entries
+            .iter()
+            .find(|entry| entry.tag == PosixAclTag::Mask)
+            .map_or(0o7, |entry| entry.perm);
+        let mut found_group = false;
+        let mut granted = None;
+        for entry in self.entries.iter() {
+            match entry.tag {
+                PosixAclTag::UserObj if uid == fsuid => {
+                    granted = Some(entry.perm);
+                    break;
+                }
+                PosixAclTag::User if Uid::new(entry.id) == fsuid => {
+                    granted = Some(entry.perm & mask);
+                    break;
+                }
+                PosixAclTag::GroupObj | PosixAclTag::Group => {
+                    let group = if entry.tag == PosixAclTag::GroupObj {
+                        gid
+                    } else {
+                        Gid::new(entry.id)
+                    };
+                    if in_group(group) {
+                        found_group = true;
+                        if entry.perm & want == want {
+                            granted = Some(entry.perm & mask);
+                            break;
+                        }
+                    }
+                }
+                PosixAclTag::Other if !found_group => {
+                    granted = Some(entry.perm);
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        if granted.is_some_and(|granted| granted & want == want) {
+            Ok(())
+        } else {
+            return_errno_with_message!(Errno::EACCES, "ACL permission check failed")
+        }
+    }
+
+    fn has_mask(&self) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| entry.tag == PosixAclTag::Mask)
+    }
+}
@@ -24,11 +24,15 @@ pub trait FsType: Send + Sync + 'static {
 
     /// Creates an instance of this FS type.
     ///
+    /// The `source` is the one given by `mount`, which names the objects other than
+    /// disks for some FS types, e.g., the tags of virtio-fs devices.
+    ///
     /// The optional `disk` argument must be provided
     /// if `self.properties()` contains `FsProperties::NEED_DISK`.
     fn create(
         &self,
         flags: FsFlags,
+        source: Option<&str>,
         args: Option<CString>,
         disk: Option<Arc<dyn BlockDevice>>,
     ) -> Result<Arc<dyn FileSystem>>;
@@ -52,6 +56,8 @@ bitflags! {
         /// But a volatile FS such as ramfs or
         /// a pseudo FS such as sysfs does not.
         const NEED_DISK = 1 << 1;
+        /// Whether a FS can be named with a subtype, e.g., `fuse.sshfs`.
+        const HAS_SUBTYPE = 1 << 2;
     }
 }
 
@@ -63,13 +69,18 @@ pub fn register(new_type: &'static dyn FsType) -> Result<()> {
 }
 
 /// Looks up a FS type.
+///
+/// A name like `fuse.sshfs` refers to the FS type `fuse` if it has subtypes.
 pub fn look_up(name: &str) -> Option<&'static dyn FsType> {
-    FS_REGISTRY
-        .get()
-        .unwrap()
-        .fs_table
-        .lock()
+    let fs_table = FS_REGISTRY.get().unwrap().fs_table.lock();
+    if let Some(fs_type) = fs_table.get(name) {
+        return Some(*fs_type);
+    }
+
+    let (name, _subtype) = name.split_once('.')?;
+    fs_table
         .get(name)
+        .filter(|fs_type| fs_type.properties().contains(FsProperties::HAS_SUBTYPE))
         .cloned()
 }
 
@@ -0,0 +1,306 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::{
+    FSCRYPT_FILE_NONCE_SIZE, FSCRYPT_KEY_IDENTIFIER_SIZE, FSCRYPT_POLICY_V1, FSCRYPT_POLICY_V2,
+    FscryptKeyring, FscryptPolicy,
+    keyring::{FSCRYPT_MAX_KEY_SIZE, FSCRYPT_MIN_KEY_SIZE, KeyRemoval},
+};
+use crate::{
+    current_userspace,
+    fs::{
+        file::InodeType,
+        vfs::{file_system::FileSystem, inode::Inode},
+    },
+    prelude::*,
+    process::{Uid, credentials::capabilities::CapSet, posix_thread::AsPosixThread},
+    util::ioctl::{RawIoctl, dispatch_ioctl},
+};
+
+/// Handles the fscrypt ioctls on an inode.
+///
+/// This function fails with `ENOTTY` if the ioctl is unknown or the filesystem does
+/// not support encryption.
+pub fn ioctl(inode: &Arc<dyn Inode>, raw_ioctl: RawIoctl) -> Result<i32> {
+    use ioctl_defs::*;
+
+    let fs = inode.fs();
+    let Some(keyring) = fs.fscrypt_keyring() else {
+        return_errno_with_message!(Errno::ENOTTY, "the ioctl command is unknown");
+    };
+    let arg = raw_ioctl.arg();
+
+    dispatch_ioctl!(match raw_ioctl {
+        SetPolicy => {
+            set_policy(inode.as_ref(), keyring, arg)?;
+        }
+        GetPolicy => {
+            if inode.fscrypt_context().is_none() {
+                return_errno_with_message!(Errno::ENODATA, "the file is not encrypted");
+            }
+            return_errno_with_message!(Errno::EINVAL, "the policy is not a v1 policy");
+        }
+        GetPolicyEx => {
+            let Some(context) = inode.fscrypt_context() else {
+                return_errno_with_message!(Errno::ENODATA, "the file is not encrypted");
+            };
+            let policy = context.policy();
+            let user_space = current_userspace!();
+            let policy_size: u64 = user_space.read_val(arg)?;
+            if policy_size < size_of::<FscryptPolicy>() as u64 {
+                return_errno_with_message!(Errno::EOVERFLOW, "the policy buffer is too small");
+            }
+            user_space.write_val(arg + size_of::<u64>(), &policy)?;
+            user_space.write_val(arg, &(size_of::<FscryptPolicy>() as u64))?;
+        }
+        cmd @ AddKey => {
+            let mut add_key_arg = cmd.read()?;
+            add_key_arg.key_spec.check()?;
+            if add_key_arg.key_id != 0 {
+                return_errno_with_message!(Errno::EINVAL, "keyring keys are not supported");
+            }
+            if add_key_arg.reserved != [0; 8] {
+                return_errno_with_message!(Errno::EINVAL, "the reserved fields are not zero");
+            }
+            let raw_size = add_key_arg.raw_size as usize;
+            if !(FSCRYPT_MIN_KEY_SIZE..=FSCRYPT_MAX_KEY_SIZE).contains(&raw_size) {
+                return_errno_with_message!(Errno::EINVAL, "the key size is invalid");
+            }
+
+            let mut raw = [0u8; FSCRYPT_MAX_KEY_SIZE];
+            current_userspace!()
+                .read_bytes(arg + size_of::<FscryptAddKeyArg>(), &mut raw[..raw_size])?;
+            let identifier = keyring.add_key(&raw[..raw_size], current_euid());
+            raw.fill(0);
+
+            add_key_arg.key_spec.identifier = identifier;
+            cmd.write(&add_key_arg)?;
+        }
+        cmd @ RemoveKey => {
+            remove_key(&fs, keyring, cmd.read()?, Some(current_euid()))
+                .and_then(|remove_key_arg| cmd.write(&remove_key_arg))?;
+        }
+        cmd @ RemoveKeyAllUsers => {
+            check_cap(CapSet::SYS_ADMIN)?;
+            remove_key(&fs, keyring, cmd.read()?, None)
+                .and_then(|remove_key_arg| cmd.write(&remove_key_arg))?;
+        }
+        cmd @ GetKeyStatus => {
+            let mut status_arg = cmd.read()?;
+            status_arg.key_spec.check()?;
+            if status_arg.reserved != [0; 6] {
+                return_errno_with_message!(Errno::EINVAL, "the reserved fields are not zero");
+            }
+
+            let status = keyring.key_status(&status_arg.key_spec.identifier, current_euid());
+            status_arg.status = if status.is_present {
+                FSCRYPT_KEY_STATUS_PRESENT
+            } else {
+                FSCRYPT_KEY_STATUS_ABSENT
+            };
+            status_arg.status_flags = if status.is_added_by_self {
+                FSCRYPT_KEY_STATUS_FLAG_ADDED_BY_SELF
+            } else {
+                0
+            };
+            status_arg.user_count = status.user_count as u32;
+            status_arg.out_reserved = [0; 13];
+            cmd.write(&status_arg)?;
+        }
+        cmd @ GetNonce => {
+            let Some(context) = inode.fscrypt_context() else {
+                return_errno_with_message!(Errno::ENODATA, "the file is not encrypted");
+            };
+            cmd.write(context.nonce())?;
+        }
+        _ => return_errno_with_message!(Errno::ENOTTY, "the ioctl command is unknown"),
+    });
+
+    Ok(0)
+}
+
+fn set_policy(inode: &dyn Inode, keyring: &FscryptKeyring, arg: Vaddr) -> Result<()> {
+    let user_space = current_userspace!();
+    let version: u8 = user_space.read_val(arg)?;
+    match version {
+        FSCRYPT_POLICY_V1 => {
+            return_errno_with_message!(Errno::EINVAL, "v1 policies are not supported")
+        }
+        FSCRYPT_POLICY_V2 => (),
+        _ => return_errno_with_message!(Errno::EINVAL, "the policy version is invalid"),
+    }
+    let policy: FscryptPolicy = user_space.read_val(arg)?;
+
+    let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+    if credentials.fsuid() != inode.metadata().uid
+        && !credentials.effective_capset().contains(CapSet::FOWNER)
+    {
+        return_errno_with_message!(Errno::EACCES, "only the owner can set the policy");
+    }
+
+    if let Some(context) = inode.fscrypt_context() {
+        if context.policy() != policy {
+            return_errno_with_message!(Errno::EEXIST, "the file has a different policy");
+        }
+        return Ok(());
+    }
+    if inode.type_() != InodeType::Dir {
+        return_errno_with_message!(Errno::ENOTDIR, "the policy can only be set on directories");
+    }
+
+    policy.validate()?;
+    let status = keyring.key_status(policy.master_key_identifier(), credentials.euid());
+    if !status.is_added_by_self && !credentials.effective_capset().contains(CapSet::FOWNER) {
+        return_errno_with_message!(Errno::ENOKEY, "the key is not added by the user");
+    }
+
+    inode.set_fscrypt_policy(&policy)
+}
+
+fn remove_key(
+    fs: &Arc<dyn FileSystem>,
+    keyring: &FscryptKeyring,
+    mut remove_key_arg: FscryptRemoveKeyArg,
+    user: Option<Uid>,
+) -> Result<FscryptRemoveKeyArg> {
+    remove_key_arg.key_spec.check()?;
+    if remove_key_arg.reserved != [0; 5] {
+        return_errno_with_message!(Errno::EINVAL, "the reserved fields are not zero");
+    }
+
+    // Writes back the dirty data while the key is still available.
+    fs.sync()?;
+
+    remove_key_arg.removal_status_flags =
+        match keyring.remove_key(&remove_key_arg.key_spec.identifier, user)? {
+            KeyRemoval::Removed => 0,
+            KeyRemoval::OtherUsers => FSCRYPT_KEY_REMOVAL_STATUS_FLAG_OTHER_USERS,
+        };
+    Ok(remove_key_arg)
+}
+
+fn current_euid() -> Uid {
+    current_thread!()
+        .as_posix_thread()
+        .unwrap()
+        .credentials()
+        .euid()
+}
+
+fn check_cap(cap: CapSet) -> Result<()> {
+    let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+    if !credentials.effective_capset().contains(cap) {
+        return_errno_with_message!(Errno::EACCES, "the operation requires the capability");
+    }
+    Ok(())
+}
+
+const FSCRYPT_KEY_SPEC_TYPE_DESCRIPTOR: u32 = 1;
+const FSCRYPT_KEY_SPEC_TYPE_IDENTIFIER: u32 = 2;
+
+const FSCRYPT_KEY_REMOVAL_STATUS_FLAG_OTHER_USERS: u32 = 0x2;
+
+const FSCRYPT_KEY_STATUS_ABSENT: u32 = 1;
+const FSCRYPT_KEY_STATUS_PRESENT: u32 = 2;
+const FSCRYPT_KEY_STATUS_FLAG_ADDED_BY_SELF: u32 = 0x1;
+
+/// The specifier of a master key (`struct fscrypt_key_specifier` in Linux).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct FscryptKeySpecifier {
+    type_: u32,
+    reserved: u32,
+    identifier: [u8; FSCRYPT_KEY_IDENTIFIER_SIZE],
+    padding: [u8; 16],
+}
+
+impl FscryptKeySpecifier {
+    fn check(&self) -> Result<()> {
+        if self.reserved != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the reserved field is not zero");
+        }
+        match self.type_ {
+            FSCRYPT_KEY_SPEC_TYPE_IDENTIFIER => Ok(()),
+            FSCRYPT_KEY_SPEC_TYPE_DESCRIPTOR => {
+                return_errno_with_message!(Errno::EOPNOTSUPP, "v1 keys are not supported")
+            }
+            _ => return_errno_with_message!(Errno::EINVAL, "the key specifier type is invalid"),
+        }
+    }
+}
+
+/// The argument of `FS_IOC_ADD_ENCRYPTION_KEY`, which is followed by the raw key.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct FscryptAddKeyArg {
+    key_spec: FscryptKeySpecifier,
+    raw_size: u32,
+    key_id: u32,
+    reserved: [u32; 8],
+}
+
+/// The argument of `FS_IOC_REMOVE_ENCRYPTION_KEY`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct FscryptRemoveKeyArg {
+    key_spec: FscryptKeySpecifier,
+    removal_status_flags: u32,
+    reserved: [u32; 5],
+}
+
+/// The argument of `FS_IOC_GET_ENCRYPTION_KEY_STATUS`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct FscryptGetKeyStatusArg {
+    key_spec: FscryptKeySpecifier,
+    reserved: [u32; 6],
+    status: u32,
+    status_flags: u32,
+    user_count: u32,
+    out_reserved: [u32; 13],
+}
+
+/// The v1 policy (`struct fscrypt_policy_v1` in Linux), whose size is encoded in the
+/// ioctls that set and get v1 policies.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct FscryptPolicyV1 {
+    version: u8,
+    contents_encryption_mode: u8,
+    filenames_encryption_mode: u8,
+    flags: u8,
+    master_key_descriptor: [u8; 8],
+}
+
+mod ioctl_defs {
+    use super::{
+        FSCRYPT_FILE_NONCE_SIZE, FscryptAddKeyArg, FscryptGetKeyStatusArg, FscryptPolicyV1,
+        FscryptRemoveKeyArg,
+    };
+    use crate::util::ioctl::{InData, InOutData, OutData, ioc};
+
+    // Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/fscrypt.h>
+
+    // Note that the directions of `FS_IOC_SET_ENCRYPTION_POLICY` and
+    // `FS_IOC_GET_ENCRYPTION_POLICY` are reversed in Linux by mistake.
+
+    /// Sets the encryption policy of an empty directory.
+    ///
+    /// The argument is either a v1 policy or a v2 policy.
+    pub(super) type SetPolicy         = ioc!(FS_IOC_SET_ENCRYPTION_POLICY,           b'f', 19, OutData<FscryptPolicyV1>);
+    /// Gets the v1 encryption policy.
+    pub(super) type GetPolicy         = ioc!(FS_IOC_GET_ENCRYPTION_POLICY,           b'f', 21, InData<FscryptPolicyV1>);
+    /// Gets the encryption policy of any version.
+    ///
+    /// The argument is the size of the buffer followed by the buffer.
+    pub(super) type GetPolicyEx       = ioc!(FS_IOC_GET_ENCRYPTION_POLICY_EX,        b'f', 22, InOutData<[u8; 9]>);
+    /// Adds a master key to the keyring of the filesystem.
+    pub(super) type AddKey            = ioc!(FS_IOC_ADD_ENCRYPTION_KEY,              b'f', 23, InOutData<FscryptAddKeyArg>);
+    /// Removes the claim of the current user to a master key.
+    pub(super) type RemoveKey         = ioc!(FS_IOC_REMOVE_ENCRYPTION_KEY,           b'f', 24, InOutData<FscryptRemoveKeyArg>);
+    /// Removes the claims of all users to a master key.
+    pub(super) type RemoveKeyAllUsers = ioc!(FS_IOC_REMOVE_ENCRYPTION_KEY_ALL_USERS, b'f', 25, InOutData<FscryptRemoveKeyArg>);
+    /// Gets the status of a master key.
+    pub(super) type GetKeyStatus      = ioc!(FS_IOC_GET_ENCRYPTION_KEY_STATUS,       b'f', 26, InOutData<FscryptGetKeyStatusArg>);
+    /// Gets the nonce of an encrypted file.
+    pub(super) type GetNonce          = ioc!(FS_IOC_GET_ENCRYPTION_NONCE,            b'f', 27, OutData<[u8; FSCRYPT_FILE_NONCE_SIZE]>);
+}
@@ -0,0 +1,176 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::{FSCRYPT_KEY_IDENTIFIER_SIZE, FscryptContext, FscryptFileKey};
+use crate::{
+    prelude::*,
+    process::Uid,
+    util::crypto::sha512::{SHA512_DIGEST_SIZE, hkdf_sha512_expand, hkdf_sha512_extract},
+};
+
+/// The minimum size of a master key.
+pub(super) const FSCRYPT_MIN_KEY_SIZE: usize = 16;
+/// The maximum size of a master key.
+pub(super) const FSCRYPT_MAX_KEY_SIZE: usize = 64;
+
+/// The size of the master key that is required by AES-256-XTS.
+const FSCRYPT_REQUIRED_KEY_SIZE: usize = 64;
+
+// The contexts of the HKDF info, which separate the keys derived for different purposes.
+const HKDF_CONTEXT_KEY_IDENTIFIER: u8 = 1;
+const HKDF_CONTEXT_PER_FILE_ENC_KEY: u8 = 2;
+
+/// The keyring of a filesystem, which holds the master keys that have been added.
+pub struct FscryptKeyring {
+    keys: Mutex<BTreeMap<[u8; FSCRYPT_KEY_IDENTIFIER_SIZE], MasterKey>>,
+}
+
+/// A master key in the keyring.
+struct MasterKey {
+    /// The pseudorandom key extracted from the raw key with HKDF.
+    prk: [u8; SHA512_DIGEST_SIZE],
+    /// The size of the raw key.
+    size: usize,
+    /// The users that have added the key.
+    ///
+    /// The key is removed only if all the users have removed it.
+    users: BTreeSet<u32>,
+}
+
+/// The status of a master key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct KeyStatus {
+    pub(super) is_present: bool,
+    pub(super) is_added_by_self: bool,
+    pub(super) user_count: usize,
+}
+
+/// The result of removing a master key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum KeyRemoval {
+    /// The key is removed.
+    Removed,
+    /// Only the claim of the user is removed, since other users have also added the key.
+    OtherUsers,
+}
+
+impl FscryptKeyring {
+    /// Creates an empty keyring.
+    pub fn new() -> Self {
+        Self {
+            keys: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Adds a master key on behalf of `user` and returns its identifier.
+    pub(super) fn add_key(&self, raw: &[u8], user: Uid) -> [u8; FSCRYPT_KEY_IDENTIFIER_SIZE] {
+        debug_assert!((FSCRYPT_MIN_KEY_SIZE..=FSCRYPT_MAX_KEY_SIZE).contains(&raw.len()));
+
+        let prk = hkdf_sha512_extract(&[], raw);
+        let mut identifier = [0u8; FSCRYPT_KEY_IDENTIFIER_SIZE];
+        hkdf_sha512_expand(
+            &prk,
+            &[b"fscrypt\0", &[HKDF_CONTEXT_KEY_IDENTIFIER]],
+            &mut identifier,
+        );
+
+        let mut keys = self.keys.lock();
+        let key = keys.entry(identifier).or_insert_with(|| MasterKey {
+            prk,
+            size: raw.len(),
+            users: BTreeSet::new(),
+        });
+        key.users.insert(user.into());
+
+        identifier
+    }
+
+    /// Removes the claim of `user` to the master key, or the claims of all users if
+    /// `user` is `None`.
+    ///
+    /// The key is removed after the last claim is removed.
+    pub(super) fn remove_key(
+        &self,
+        identifier: &[u8; FSCRYPT_KEY_IDENTIFIER_SIZE],
+        user: Option<Uid>,
+    ) -> Result<KeyRemoval> {
+        let mut keys = self.keys.lock();
+        let Some(key) = keys.get_mut(identifier) else {
+            return_errno_with_message!(Errno::ENOKEY, "the key is not in the keyring");
+        };
+
+        if let Some(user) = user {
+            if !key.users.remove(&u32::from(user)) {
+                return_errno_with_message!(Errno::ENOKEY, "the key is not added by the user");
+            }
+            if !key.users.is_empty() {
+                return Ok(KeyRemoval::OtherUsers);
+            }
+        }
+
+        keys.remove(identifier);
+        Ok(KeyRemoval::Removed)
+    }
+
+    /// Returns the status of the master key with respect to `user`.
+    pub(super) fn key_status(
+        &self,
+        identifier: &[u8; FSCRYPT_KEY_IDENTIFIER_SIZE],
+        user: Uid,
+    ) -> KeyStatus {
+        let keys = self.keys.lock();
+        match keys.get(identifier) {
+            Some(key) => KeyStatus {
+                is_present: true,
+                is_added_by_self: key.users.contains(&u32::from(user)),
+                user_count: key.users.len(),
+            },
+            None => KeyStatus {
+                is_present: false,
+                is_added_by_self: false,
+                user_count: 0,
+            },
+        }
+    }
+
+    /// Derives the per-file key of the file with the encryption context.
+    ///
+    /// This method fails with `ENOKEY` if the master key is not in the keyring.
+    /// The per-file key is not cached, so that the file becomes inaccessible as soon as
+    /// the master key is removed.
+    pub fn file_key(&self, context: &FscryptContext) -> Result<FscryptFileKey> {
+        let keys = self.keys.lock();
+        let Some(master_key) = keys.get(&context.master_key_identifier) else {
+            return_errno_with_message!(Errno::ENOKEY, "the master key is not in the keyring");
+        };
+        if master_key.size < FSCRYPT_REQUIRED_KEY_SIZE {
+            return_errno_with_message!(Errno::ENOKEY, "the master key is too short");
+        }
+
+        let mut key = [0u8; 64];
+        hkdf_sha512_expand(
+            &master_key.prk,
+            &[
+                b"fscrypt\0",
+                &[HKDF_CONTEXT_PER_FILE_ENC_KEY],
+                &context.nonce,
+            ],
+            &mut key,
+        );
+        Ok(FscryptFileKey::new(&key, context.flags))
+    }
+}
+
+impl Default for FscryptKeyring {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for FscryptKeyring {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        // The keys must never be printed.
+        f.debug_struct("FscryptKeyring")
+            .field("nr_keys", &self.keys.lock().len())
+            .finish()
+    }
+}
@@ -0,0 +1,239 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Filesystem-level encryption in the style of Linux's fscrypt.
+//!
+//! An empty directory can be assigned an encryption policy, which names a master key by
+//! its identifier. The files created in the directory inherit the policy, and each of
+//! them gets an encryption context, which additionally holds a random nonce. The nonce
+//! and the master key derive the per-file key, which encrypts the contents of a regular
+//! file or the entry names of a directory.
+//!
+//! The master keys are added to and removed from the keyring of a filesystem via ioctls.
+//! Without the master key, the contents of an encrypted file cannot be accessed and the
+//! entry names of an encrypted directory are shown as "no-key names", which are encoded
+//! from the ciphertext.
+//!
+//! Only the v2 policies with AES-256-XTS for contents and AES-256-CTS for names are
+//! supported. The v1 policies, which are deprecated in Linux, are rejected.
+//!
+//! Reference: <https://docs.kernel.org/filesystems/fscrypt.html>
+
+use self::name::encrypted_name_len;
+pub use self::{ioctl::ioctl, keyring::FscryptKeyring, name::NoKeyName};
+use crate::{
+    prelude::*,
+    util::{
+        crypto::{
+            aes::AES_BLOCK_SIZE,
+            api::{Skcipher, SkcipherAlg},
+        },
+        random::getrandom,
+    },
+};
+
+mod ioctl;
+mod keyring;
+mod name;
+
+/// The size of the identifier of a master key.
+pub const FSCRYPT_KEY_IDENTIFIER_SIZE: usize = 16;
+
+/// The size of the nonce of an encryption context.
+pub const FSCRYPT_FILE_NONCE_SIZE: usize = 16;
+
+const FSCRYPT_POLICY_V1: u8 = 0;
+const FSCRYPT_POLICY_V2: u8 = 2;
+const FSCRYPT_CONTEXT_V2: u8 = 2;
+
+const FSCRYPT_MODE_AES_256_XTS: u8 = 1;
+const FSCRYPT_MODE_AES_256_CTS: u8 = 4;
+
+const FSCRYPT_POLICY_FLAGS_PAD_MASK: u8 = 0x03;
+
+/// An encryption policy (`struct fscrypt_policy_v2` in Linux).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Pod)]
+pub struct FscryptPolicy {
+    version: u8,
+    contents_encryption_mode: u8,
+    filenames_encryption_mode: u8,
+    flags: u8,
+    reserved: [u8; 4],
+    master_key_identifier: [u8; FSCRYPT_KEY_IDENTIFIER_SIZE],
+}
+
+impl FscryptPolicy {
+    /// Checks whether the policy is supported.
+    fn validate(&self) -> Result<()> {
+        if self.version != FSCRYPT_POLICY_V2 {
+            return_errno_with_message!(Errno::EINVAL, "the policy version is not supported");
+        }
+        if self.contents_encryption_mode != FSCRYPT_MODE_AES_256_XTS
+            || self.filenames_encryption_mode != FSCRYPT_MODE_AES_256_CTS
+        {
+            return_errno_with_message!(Errno::EINVAL, "the encryption modes are not supported");
+        }
+        if self.flags & !FSCRYPT_POLICY_FLAGS_PAD_MASK != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the policy flags are not supported");
+        }
+        if self.reserved != [0; 4] {
+            return_errno_with_message!(Errno::EINVAL, "the reserved fields are not zero");
+        }
+        Ok(())
+    }
+
+    /// Returns the identifier of the master key.
+    pub fn master_key_identifier(&self) -> &[u8; FSCRYPT_KEY_IDENTIFIER_SIZE] {
+        &self.master_key_identifier
+    }
+}
+
+/// An encryption context, which is stored with each encrypted inode
+/// (`struct fscrypt_context_v2` in Linux).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Pod)]
+pub struct FscryptContext {
+    version: u8,
+    contents_encryption_mode: u8,
+    filenames_encryption_mode: u8,
+    flags: u8,
+    reserved: [u8; 4],
+    master_key_identifier: [u8; FSCRYPT_KEY_IDENTIFIER_SIZE],
+    nonce: [u8; FSCRYPT_FILE_NONCE_SIZE],
+}
+
+impl FscryptContext {
+    /// Creates a context with the policy and a random nonce.
+    pub fn new(policy: &FscryptPolicy) -> Self {
+        let mut nonce = [0u8; FSCRYPT_FILE_NONCE_SIZE];
+        getrandom(&mut nonce);
+
+        Self {
+            version: FSCRYPT_CONTEXT_V2,
+            contents_encryption_mode: policy.contents_encryption_mode,
+            filenames_encryption_mode: policy.filenames_encryption_mode,
+            flags: policy.flags,
+            reserved: [0; 4],
+            master_key_identifier: policy.master_key_identifier,
+            nonce,
+        }
+    }
+
+    /// Parses a context that is loaded from the disk.
+    pub fn from_disk_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != size_of::<Self>() {
+            return_errno_with_message!(Errno::EUCLEAN, "the encryption context is corrupted");
+        }
+        let context = Self::from_bytes(bytes);
+        if context.version != FSCRYPT_CONTEXT_V2 || context.policy().validate().is_err() {
+            return_errno_with_message!(Errno::EUCLEAN, "the encryption context is not supported");
+        }
+        Ok(context)
+    }
+
+    /// Returns the policy of the context.
+    pub fn policy(&self) -> FscryptPolicy {
+        FscryptPolicy {
+            version: FSCRYPT_POLICY_V2,
+            contents_encryption_mode: self.contents_encryption_mode,
+            filenames_encryption_mode: self.filenames_encryption_mode,
+            flags: self.flags,
+            reserved: [0; 4],
+            master_key_identifier: self.master_key_identifier,
+        }
+    }
+
+    /// Returns the context for a new inode that is created in the directory with this
+    /// context.
+    pub fn inherit(&self) -> Self {
+        Self::new(&self.policy())
+    }
+
+    /// Returns the nonce.
+    pub fn nonce(&self) -> &[u8; FSCRYPT_FILE_NONCE_SIZE] {
+        &self.nonce
+    }
+
+    /// Returns whether the inodes with this context and those with `other` can be
+    /// linked to the same directory.
+    pub fn has_same_policy(&self, other: &Self) -> bool {
+        self.policy() == other.policy()
+    }
+}
+
+/// The per-file key that is derived from a master key and the nonce of a file.
+pub struct FscryptFileKey {
+    /// The cipher of the contents, which is AES-256-XTS with the whole key.
+    contents_cipher: Box<dyn Skcipher>,
+    /// The cipher of the names, which is AES-256-CTS with the first half of the key.
+    names_cipher: Box<dyn Skcipher>,
+    flags: u8,
+}
+
+impl FscryptFileKey {
+    fn new(key: &[u8; 64], flags: u8) -> Self {
+        // The key sizes are valid for both ciphers, so the creation never fails.
+        Self {
+            contents_cipher: SkcipherAlg::XtsAes.new_cipher(key).unwrap(),
+            names_cipher: SkcipherAlg::CtsCbcAes.new_cipher(&key[..32]).unwrap(),
+            flags,
+        }
+    }
+
+    /// Encrypts the data unit (i.e., a block) at `index` in place.
+    pub fn encrypt_block(&self, index: u64, data: &mut [u8]) {
+        // Data units are multiples of the AES block size, so the encryption never fails.
+        self.contents_cipher
+            .encrypt(&mut block_iv(index), data)
+            .unwrap();
+    }
+
+    /// Decrypts the data unit (i.e., a block) at `index` in place.
+    pub fn decrypt_block(&self, index: u64, data: &mut [u8]) {
+        self.contents_cipher
+            .decrypt(&mut block_iv(index), data)
+            .unwrap();
+    }
+
+    /// Encrypts an entry name of a directory.
+    ///
+    /// The name is padded with zeros before the encryption, so that names of similar
+    /// lengths cannot be told apart.
+    pub fn encrypt_name(&self, name: &[u8]) -> Vec<u8> {
+        let mut ciphertext = name.to_vec();
+        ciphertext.resize(encrypted_name_len(name.len(), self.flags), 0);
+        // The padded name has at least one block, so the encryption never fails.
+        self.names_cipher
+            .encrypt(&mut [0; AES_BLOCK_SIZE], &mut ciphertext)
+            .unwrap();
+        ciphertext
+    }
+
+    /// Decrypts an entry name of a directory.
+    pub fn decrypt_name(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        if ciphertext.len() < AES_BLOCK_SIZE {
+            return_errno_with_message!(Errno::EUCLEAN, "the encrypted name is too short");
+        }
+
+        let mut name = ciphertext.to_vec();
+        self.names_cipher
+            .decrypt(&mut [0; AES_BLOCK_SIZE], &mut name)
+            .unwrap();
+        let name_len = name
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(name.len());
+        if name_len == 0 {
+            return_errno_with_message!(Errno::EUCLEAN, "the encrypted name is empty");
+        }
+        name.truncate(name_len);
+        Ok(name)
+    }
+}
+
+/// Returns the IV of the data unit at `index`, which is the little-endian index.
+fn block_iv(index: u64) -> [u8; AES_BLOCK_SIZE] {
+    let mut iv = [0u8; AES_BLOCK_SIZE];
+    iv[..size_of::<u64>()].copy_from_slice(&index.to_le_bytes());
+    iv
+}
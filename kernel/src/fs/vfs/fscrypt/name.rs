@@ -0,0 +1,141 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::FSCRYPT_POLICY_FLAGS_PAD_MASK;
+use crate::{
+    fs::utils::NAME_MAX,
+    prelude::*,
+    util::crypto::{aes::AES_BLOCK_SIZE, sha512::sha512},
+};
+
+/// The maximum size of the ciphertext that is encoded in a no-key name as a whole.
+///
+/// The encoded name of this size has 252 characters, which fits in `NAME_MAX`.
+const NOKEY_NAME_MAX_SIZE: usize = 189;
+/// The size of the digest that replaces the tail of a long ciphertext.
+const NOKEY_NAME_DIGEST_SIZE: usize = 32;
+/// The size of the ciphertext prefix that is kept in a no-key name for a long ciphertext.
+const NOKEY_NAME_PREFIX_SIZE: usize = NOKEY_NAME_MAX_SIZE - NOKEY_NAME_DIGEST_SIZE;
+
+/// Returns the length of the encrypted name for a name of `len` bytes.
+pub(super) fn encrypted_name_len(len: usize, flags: u8) -> usize {
+    let padding = 4 << (flags & FSCRYPT_POLICY_FLAGS_PAD_MASK);
+    len.max(AES_BLOCK_SIZE)
+        .next_multiple_of(padding)
+        .min(NAME_MAX)
+}
+
+/// A "no-key name", which represents an encrypted name if the key is absent.
+///
+/// A short ciphertext is encoded as is. A long ciphertext is encoded with its prefix and
+/// the digest of the rest, so the no-key name may not identify the ciphertext uniquely.
+/// The encoding is base64url without padding.
+pub struct NoKeyName {
+    bytes: Vec<u8>,
+}
+
+impl NoKeyName {
+    /// Creates the no-key name of the ciphertext.
+    pub fn new(ciphertext: &[u8]) -> Self {
+        let bytes = if ciphertext.len() <= NOKEY_NAME_MAX_SIZE {
+            ciphertext.to_vec()
+        } else {
+            let mut bytes = ciphertext[..NOKEY_NAME_PREFIX_SIZE].to_vec();
+            let digest = sha512(&ciphertext[NOKEY_NAME_PREFIX_SIZE..]);
+            bytes.extend_from_slice(&digest[..NOKEY_NAME_DIGEST_SIZE]);
+            bytes
+        };
+        Self { bytes }
+    }
+
+    /// Parses a no-key name that is given by the user.
+    ///
+    /// Returns `None` if the name cannot be a no-key name.
+    pub fn parse(name: &str) -> Option<Self> {
+        let bytes = base64url_decode(name.as_bytes())?;
+        if bytes.len() < AES_BLOCK_SIZE || bytes.len() > NOKEY_NAME_MAX_SIZE {
+            return None;
+        }
+        Some(Self { bytes })
+    }
+
+    /// Returns whether the no-key name represents the ciphertext.
+    pub fn matches(&self, ciphertext: &[u8]) -> bool {
+        if ciphertext.len() <= NOKEY_NAME_MAX_SIZE {
+            return self.bytes == ciphertext;
+        }
+
+        self.bytes.len() == NOKEY_NAME_MAX_SIZE
+            && self.bytes[..NOKEY_NAME_PREFIX_SIZE] == ciphertext[..NOKEY_NAME_PREFIX_SIZE]
+            && self.bytes[NOKEY_NAME_PREFIX_SIZE..]
+                == sha512(&ciphertext[NOKEY_NAME_PREFIX_SIZE..])[..NOKEY_NAME_DIGEST_SIZE]
+    }
+
+    /// Encodes the no-key name as a string.
+    pub fn encode(&self) -> String {
+        base64url_encode(&self.bytes)
+    }
+}
+
+const BASE64URL_CHARS: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+fn base64url_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let mut group = [0u8; 3];
+        group[..chunk.len()].copy_from_slice(chunk);
+        let bits = u32::from_be_bytes([0, group[0], group[1], group[2]]);
+        for i in 0..=chunk.len() {
+            let index = (bits >> (18 - 6 * i)) & 0x3f;
+            encoded.push(BASE64URL_CHARS[index as usize] as char);
+        }
+    }
+    encoded
+}
+
+fn base64url_decode(encoded: &[u8]) -> Option<Vec<u8>> {
+    if encoded.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(encoded.len() * 3 / 4);
+    for chunk in encoded.chunks(4) {
+        let mut bits = 0u32;
+        for (i, char) in chunk.iter().enumerate() {
+            let index = BASE64URL_CHARS.iter().position(|c| c == char)?;
+            bits |= (index as u32) << (18 - 6 * i);
+        }
+        let group = bits.to_be_bytes();
+        bytes.extend_from_slice(&group[1..chunk.len()]);
+    }
+    Some(bytes)
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    #[ktest]
+    fn base64url_round_trip() {
+        for len in 0..8 {
+            let bytes: Vec<u8> = (0..len).map(|i| (i * 77 + 200) as u8).collect();
+            let encoded = base64url_encode(&bytes);
+            assert_eq!(encoded.len(), (len * 4).div_ceil(3));
+            assert_eq!(base64url_decode(encoded.as_bytes()).unwrap(), bytes);
+        }
+        assert_eq!(base64url_encode(b"\xfb\xff"), "-_8");
+        assert!(base64url_decode(b"abcde").is_none());
+        assert!(base64url_decode(b"ab=c").is_none());
+    }
+
+    #[ktest]
+    fn long_nokey_name() {
+        let ciphertext = [0x5a; 240];
+        let nokey_name = NoKeyName::new(&ciphertext);
+        let parsed = NoKeyName::parse(&nokey_name.encode()).unwrap();
+        assert!(parsed.matches(&ciphertext));
+        assert!(!parsed.matches(&[0x5a; 224]));
+    }
+}
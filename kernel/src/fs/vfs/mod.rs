@@ -6,13 +6,15 @@
 //! serving as the bridge between system calls and concrete file systems.
 
 mod fs_apis;
+pub mod fscrypt;
 pub mod notify;
 pub mod page_cache;
 pub mod path;
+pub mod quota;
 pub mod range_lock;
 
 // Re-export commonly used abstractions from `fs_apis`
-pub use fs_apis::{file_system, inode, inode_ext, registry, xattr};
+pub use fs_apis::{file_system, inode, inode_ext, posix_acl, registry, xattr};
 
 pub(super) fn init() {
     fs_apis::init();
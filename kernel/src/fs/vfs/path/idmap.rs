@@ -0,0 +1,77 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    prelude::*,
+    process::{Gid, Uid, UserNamespace},
+};
+
+/// The ID mapping of an idmapped mount.
+///
+/// An idmapped mount translates the UIDs and GIDs stored in the file system
+/// into other UIDs and GIDs when the files are accessed through the mount.
+/// The translation follows the ID maps of a user namespace: an ID `x` stored
+/// in the file system is seen as the ID that `x` maps to outside the user
+/// namespace, and vice versa.
+///
+/// Reference: <https://docs.kernel.org/filesystems/idmappings.html>
+pub struct MountIdmap {
+    user_ns: Arc<UserNamespace>,
+}
+
+impl MountIdmap {
+    /// Creates an ID mapping that follows the ID maps of the user namespace.
+    pub fn new(user_ns: Arc<UserNamespace>) -> Self {
+        Self { user_ns }
+    }
+
+    /// Maps a UID stored in the file system to the UID seen through the mount.
+    ///
+    /// UIDs that cannot be mapped are seen as [`Uid::OVERFLOW`].
+    pub fn map_uid_to_mount(&self, uid: Uid) -> Uid {
+        self.user_ns.map_uid_down(uid).unwrap_or(Uid::OVERFLOW)
+    }
+
+    /// Maps a GID stored in the file system to the GID seen through the mount.
+    ///
+    /// GIDs that cannot be mapped are seen as [`Gid::OVERFLOW`].
+    pub fn map_gid_to_mount(&self, gid: Gid) -> Gid {
+        self.user_ns.map_gid_down(gid).unwrap_or(Gid::OVERFLOW)
+    }
+
+    /// Maps a UID seen through the mount to the UID stored in the file system.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EOVERFLOW` if the UID cannot be represented in the file system.
+    pub fn map_uid_from_mount(&self, uid: Uid) -> Result<Uid> {
+        self.user_ns.map_uid_up(uid).ok_or_else(|| {
+            Error::with_message(
+                Errno::EOVERFLOW,
+                "the UID is not mapped in the idmapped mount",
+            )
+        })
+    }
+
+    /// Maps a GID seen through the mount to the GID stored in the file system.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EOVERFLOW` if the GID cannot be represented in the file system.
+    pub fn map_gid_from_mount(&self, gid: Gid) -> Result<Gid> {
+        self.user_ns.map_gid_up(gid).ok_or_else(|| {
+            Error::with_message(
+                Errno::EOVERFLOW,
+                "the GID is not mapped in the idmapped mount",
+            )
+        })
+    }
+}
+
+impl Debug for MountIdmap {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("MountIdmap")
+            .field("uid_map", self.user_ns.uid_map())
+            .field("gid_map", self.user_ns.gid_map())
+            .finish()
+    }
+}
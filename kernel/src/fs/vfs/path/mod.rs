@@ -6,8 +6,9 @@ use core::time::Duration;
 
 pub(in crate::fs) use dentry::Dentry;
 use dentry::DirDentry;
+pub use idmap::MountIdmap;
 use inherit_methods_macro::inherit_methods;
-pub use mount::{Mount, MountPropType, PerMountFlags};
+pub use mount::{Mount, MountAttrChange, MountPropType, PerMountFlags};
 pub use mount_namespace::MountNamespace;
 pub use resolver::{AT_FDCWD, AbsPathResult, FsPath, LookupResult, PathResolver, SplitPath};
 
@@ -15,18 +16,22 @@ use crate::{
     fs::{
         file::{
             CreationFlags, InodeHandle, InodeMode, InodeType, OpenArgs, Permission, StatusFlags,
+            lease,
         },
         vfs::{
             file_system::{FileSystem, FsFlags},
-            inode::{Inode, Metadata, MknodType},
+            inode::{Inode, Metadata, MknodType, check_dac_permission},
+            posix_acl::PosixAclType,
             xattr::{XattrName, XattrNamespace, XattrSetFlags},
         },
     },
     prelude::*,
     process::{Gid, Uid},
+    security::lsm,
 };
 
 mod dentry;
+mod idmap;
 mod mount;
 mod mount_namespace;
 mod resolver;
@@ -58,18 +63,19 @@ impl Path {
 
     /// Creates a new `Path` to represent the child directory of a file system.
     pub fn new_fs_child(&self, name: &str, type_: InodeType, mode: InodeMode) -> Result<Self> {
-        if self
-            .inode()
-            .check_permission(Permission::MAY_WRITE)
-            .is_err()
-        {
+        if self.check_permission(Permission::MAY_WRITE).is_err() {
             return_errno!(Errno::EACCES);
         }
+        self.check_writable_mount()?;
+        lsm::path_mknod(self, name, type_)?;
+
         let new_child_dentry = self
             .dentry
             .as_dir_dentry_or_err()?
             .create(name, type_, mode)?;
-        Ok(Self::new(self.mount.clone(), new_child_dentry))
+        let new_child = Self::new(self.mount.clone(), new_child_dentry);
+        new_child.apply_idmap_to_new_inode()?;
+        Ok(new_child)
     }
 
     /// Creates a new pseudo `Path`.
@@ -129,11 +135,24 @@ impl Path {
             );
         }
 
-        if inode_type.is_regular_file()
-            && creation_flags.contains(CreationFlags::O_TRUNC)
-            && !status_flags.contains(StatusFlags::O_PATH)
+        if !status_flags.contains(StatusFlags::O_PATH)
+            && (open_args.access_mode.is_writable()
+                || creation_flags.contains(CreationFlags::O_TRUNC))
+            && !inode_type.is_device()
         {
-            self.resize(0)?;
+            self.check_writable_mount()?;
+        }
+
+        if inode_type.is_regular_file() && !status_flags.contains(StatusFlags::O_PATH) {
+            let is_trunc = creation_flags.contains(CreationFlags::O_TRUNC);
+            lease::break_lease(
+                inode,
+                open_args.access_mode.is_writable() || is_trunc,
+                status_flags.contains(StatusFlags::O_NONBLOCK),
+            )?;
+            if is_trunc {
+                self.resize(0)?;
+            }
         }
 
         InodeHandle::new(self.clone(), open_args.access_mode, *status_flags)
@@ -156,6 +175,77 @@ impl Path {
         Some(Self::new(self.mount.clone(), parent))
     }
 
+    /// Gets the parent `Path`, crossing mount boundaries.
+    ///
+    /// If the current path is the root of a mount, the parent is that of the mountpoint in the
+    /// parent mount. Unlike [`PathResolver`], this method does not stop at the root directory of
+    /// the current thread. It returns `None` if the current path is the root of the mount tree or
+    /// a pseudo path.
+    pub fn effective_parent(&self) -> Option<Self> {
+        self.lowest_mountpoint().parent_within_mount()
+    }
+
+    /// Gets the full name of the `Path`, which is the absolute path from the root of the mount
+    /// tree.
+    ///
+    /// Unlike [`PathResolver::make_abs_path`], this method does not stop at the root directory
+    /// of the current thread. For a pseudo path, this method returns its name.
+    pub fn full_name(&self) -> String {
+        if self.is_pseudo() {
+            return self.name();
+        }
+
+        let mut components = Vec::new();
+        let mut current = self.lowest_mountpoint();
+        while let Some(parent) = current.parent_within_mount() {
+            components.push(current.name());
+            current = parent.lowest_mountpoint();
+        }
+
+        if components.is_empty() {
+            return "/".to_string();
+        }
+        components.iter().rev().fold(String::new(), |mut full_name, component| {
+            full_name.push('/');
+            full_name.push_str(component);
+            full_name
+        })
+    }
+
+    /// Gets the `Path` that is the same file as the current one, but is not the root of a mount.
+    ///
+    /// If the current path is the root of a mount, this method returns the mountpoint in the
+    /// parent mount, repeatedly. It returns the root of the mount tree if no such path exists.
+    fn lowest_mountpoint(&self) -> Self {
+        let mut current = self.clone();
+
+        while current.is_mount_root() {
+            let Some(parent_mount) = current.mount.parent().and_then(|mount| mount.upgrade()) else {
+                break;
+            };
+            let Some(mountpoint) = current.mount.mountpoint() else {
+                break;
+            };
+            current = Self::new(parent_mount, mountpoint);
+        }
+
+        current
+    }
+
+    /// Gets the child `Path` with `name` within the same mount.
+    ///
+    /// Unlike [`PathResolver::lookup_at_path`], this method neither checks the permissions
+    /// nor crosses mount boundaries. It is used by the kernel to maintain the files of
+    /// a file system through one of its mounts.
+    pub(in crate::fs) fn child_within_mount(&self, name: &str) -> Result<Self> {
+        let dir_dentry = self.dentry.as_dir_dentry_or_err()?;
+        let child = match dir_dentry.lookup_via_cache(name)? {
+            Some(child) => child,
+            None => dir_dentry.lookup_via_fs(name)?,
+        };
+        Ok(Self::new(self.mount.clone(), child))
+    }
+
     /// Gets the top `Path` of the current.
     ///
     /// Used when different file systems are mounted on the same mount point.
@@ -219,8 +309,73 @@ impl Path {
         }
     }
 
+    /// Checks the permissions of the current thread to access the `Path`.
+    ///
+    /// Unlike [`Inode::check_permission`], this method takes the ID mapping of the
+    /// mount into account, and it also consults the security modules.
+    pub fn check_permission(&self, perm: Permission) -> Result<()> {
+        self.check_dac_permission(perm)?;
+        lsm::inode_permission(self, perm)
+    }
+
+    fn check_dac_permission(&self, perm: Permission) -> Result<()> {
+        let Some(idmap) = self.mount.idmap() else {
+            return self.inode().check_permission(perm);
+        };
+
+        let metadata = self.inode().metadata();
+        let mut acl = self.inode().posix_acl(PosixAclType::Access)?;
+        if let Some(acl) = acl.as_mut() {
+            acl.map_ids(
+                |uid| idmap.map_uid_to_mount(uid),
+                |gid| idmap.map_gid_to_mount(gid),
+            );
+        }
+        check_dac_permission(
+            metadata.mode,
+            idmap.map_uid_to_mount(metadata.uid),
+            idmap.map_gid_to_mount(metadata.gid),
+            acl.as_ref(),
+            perm,
+        )
+    }
+
+    /// Returns the flags of the mount of the `Path`.
+    pub fn mount_flags(&self) -> PerMountFlags {
+        self.mount.flags()
+    }
+
+    /// Checks whether the mount of the `Path` allows modifications.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EROFS` if the mount is read-only.
+    fn check_writable_mount(&self) -> Result<()> {
+        if self.mount.flags().contains(PerMountFlags::RDONLY) {
+            return_errno_with_message!(Errno::EROFS, "the mount is read-only");
+        }
+        Ok(())
+    }
+
+    /// Fixes up the owners of a newly created inode for idmapped mounts.
+    ///
+    /// The file system assigns the owners of a new inode from the credentials of the
+    /// current thread, which are IDs seen through the mount. For idmapped mounts, these
+    /// IDs must be mapped back before being stored in the file system.
+    fn apply_idmap_to_new_inode(&self) -> Result<()> {
+        let Some(idmap) = self.mount.idmap() else {
+            return Ok(());
+        };
+
+        let inode = self.inode();
+        let metadata = inode.metadata();
+        inode.set_owner(idmap.map_uid_from_mount(metadata.uid)?)?;
+        inode.set_group(idmap.map_gid_from_mount(metadata.gid)?)?;
+        Ok(())
+    }
+
     /// Returns true if the `Path` represents a pseudo file.
-    fn is_pseudo(&self) -> bool {
+    pub fn is_pseudo(&self) -> bool {
         self.dentry.is_pseudo()
     }
 
@@ -342,7 +497,7 @@ impl Path {
     ///
     /// Returns `ENOTDIR` if the `dst_path` is not a directory.
     /// Returns `EINVAL` if either source or destination path is not in the
-    /// current mount namespace.
+    /// current mount namespace, or if the source mount is unbindable.
     pub fn bind_mount_to(&self, dst_path: &Self, recursive: bool, ctx: &Context) -> Result<()> {
         let can_bind = {
             let src_is_dir = self.type_() == InodeType::Dir;
@@ -371,6 +526,10 @@ impl Path {
             );
         }
 
+        if self.mount.is_unbindable() {
+            return_errno_with_message!(Errno::EINVAL, "the source mount is unbindable");
+        }
+
         let new_mount = self.mount.clone_mount_tree(&self.dentry, None, recursive);
         new_mount.graft_mount_tree(dst_path);
         Ok(())
@@ -385,6 +544,8 @@ impl Path {
     /// - The current path is not a mount root.
     /// - The mount of the current path is the root mount.
     /// - Either source or destination path is not in the current mount namespace
+    /// - The parent mount of the current path is shared.
+    /// - The destination mount is shared and the moved mount tree contains unbindable mounts.
     pub fn move_mount_to(&self, dst_path: &Self, ctx: &Context) -> Result<()> {
         if !self.is_mount_root() {
             return_errno_with_message!(Errno::EINVAL, "the path is not a mount root");
@@ -419,6 +580,17 @@ impl Path {
             );
         }
 
+        let parent_mount = self.mount.parent().unwrap().upgrade().unwrap();
+        if parent_mount.peer_group_id().is_some() {
+            return_errno_with_message!(Errno::EINVAL, "the parent mount is shared");
+        }
+        if dst_path.mount.peer_group_id().is_some() && self.mount.contains_unbindable() {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "unbindable mounts cannot be moved under a shared mount"
+            );
+        }
+
         self.mount.graft_mount_tree(dst_path);
 
         Ok(())
@@ -445,6 +617,35 @@ impl Path {
 
         Ok(())
     }
+
+    /// Changes the attributes of the mount of this `Path`.
+    ///
+    /// If `recursive` is true, the attributes of all the descendant mounts are
+    /// changed as well.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EINVAL` if the current path is not a mount root or if the current path
+    /// is not in the current mount namespace.
+    /// Returns `EPERM` if an ID mapping is requested for an already idmapped mount.
+    pub fn set_mount_attr(
+        &self,
+        attr: &MountAttrChange,
+        recursive: bool,
+        ctx: &Context,
+    ) -> Result<()> {
+        if !self.is_mount_root() {
+            return_errno_with_message!(Errno::EINVAL, "the path is not a mount root");
+        };
+
+        let current_ns_proxy = ctx.thread_local.borrow_ns_proxy();
+        let current_mnt_ns = current_ns_proxy.unwrap().mnt_ns();
+        if !current_mnt_ns.owns(&self.mount) {
+            return_errno_with_message!(Errno::EINVAL, "the path is not in this mount namespace");
+        }
+
+        self.mount.set_attr(attr, recursive)
+    }
 }
 
 // Methods inherited from `Dentry`.
@@ -455,11 +656,16 @@ impl Path {
 
     /// Creates a `Path` by making an inode of the `type_` with the `mode`.
     pub fn mknod(&self, name: &str, mode: InodeMode, type_: MknodType) -> Result<Self> {
+        self.check_writable_mount()?;
+        lsm::path_mknod(self, name, type_.inode_type())?;
+
         let inner = self
             .dentry
             .as_dir_dentry_or_err()?
             .mknod(name, mode, type_)?;
-        Ok(Self::new(self.mount.clone(), inner))
+        let new_path = Self::new(self.mount.clone(), inner);
+        new_path.apply_idmap_to_new_inode()?;
+        Ok(new_path)
     }
 
     /// Links a new name for the `Path`.
@@ -467,17 +673,25 @@ impl Path {
         if !Arc::ptr_eq(&old.mount, &self.mount) {
             return_errno_with_message!(Errno::EXDEV, "the operation cannot cross mounts");
         }
+        self.check_writable_mount()?;
+        lsm::path_link(old, self, name)?;
 
         self.dentry.as_dir_dentry_or_err()?.link(old.inode(), name)
     }
 
     /// Unlinks a name from the `Path`.
     pub fn unlink(&self, name: &str) -> Result<()> {
+        self.check_writable_mount()?;
+        lsm::path_unlink(self, name)?;
+
         self.dentry.as_dir_dentry_or_err()?.unlink(name)
     }
 
     /// Removes a directory by `rmdir()` the inner inode.
     pub fn rmdir(&self, name: &str) -> Result<()> {
+        self.check_writable_mount()?;
+        lsm::path_rmdir(self, name)?;
+
         self.dentry.as_dir_dentry_or_err()?.rmdir(name)
     }
 
@@ -486,45 +700,124 @@ impl Path {
         if !Arc::ptr_eq(&self.mount, &new_dir.mount) {
             return_errno_with_message!(Errno::EXDEV, "the operation cannot cross mounts");
         }
+        self.check_writable_mount()?;
+        {
+            let old = self.child_within_mount(old_name)?;
+            let new = new_dir.child_within_mount(new_name).ok();
+            lsm::path_rename(self, &old, new_dir, new_name, new.as_ref())?;
+        }
 
         DirDentry::rename(&self.dentry, old_name, &new_dir.dentry, new_name)
     }
 }
 
+// Methods that respect the attributes of the mount.
+impl Path {
+    /// Returns the metadata of the `Path`.
+    ///
+    /// For idmapped mounts, the owners are the ones seen through the mount.
+    pub fn metadata(&self) -> Metadata {
+        let mut metadata = self.inode().metadata();
+        if let Some(idmap) = self.mount.idmap() {
+            metadata.uid = idmap.map_uid_to_mount(metadata.uid);
+            metadata.gid = idmap.map_gid_to_mount(metadata.gid);
+        }
+        metadata
+    }
+
+    pub fn set_mode(&self, mode: InodeMode) -> Result<()> {
+        self.check_writable_mount()?;
+        self.inode().set_mode(mode)
+    }
+
+    pub fn resize(&self, size: usize) -> Result<()> {
+        self.check_writable_mount()?;
+        lsm::path_truncate(self)?;
+        self.inode().resize(size)
+    }
+
+    /// Returns the owner of the `Path`.
+    ///
+    /// For idmapped mounts, the owner is the one seen through the mount.
+    pub fn owner(&self) -> Result<Uid> {
+        let uid = self.inode().owner()?;
+        match self.mount.idmap() {
+            Some(idmap) => Ok(idmap.map_uid_to_mount(uid)),
+            None => Ok(uid),
+        }
+    }
+
+    /// Sets the owner of the `Path`.
+    ///
+    /// For idmapped mounts, `uid` is the owner seen through the mount.
+    pub fn set_owner(&self, uid: Uid) -> Result<()> {
+        self.check_writable_mount()?;
+        let uid = match self.mount.idmap() {
+            Some(idmap) => idmap.map_uid_from_mount(uid)?,
+            None => uid,
+        };
+        self.inode().set_owner(uid)
+    }
+
+    /// Returns the group of the `Path`.
+    ///
+    /// For idmapped mounts, the group is the one seen through the mount.
+    pub fn group(&self) -> Result<Gid> {
+        let gid = self.inode().group()?;
+        match self.mount.idmap() {
+            Some(idmap) => Ok(idmap.map_gid_to_mount(gid)),
+            None => Ok(gid),
+        }
+    }
+
+    /// Sets the group of the `Path`.
+    ///
+    /// For idmapped mounts, `gid` is the group seen through the mount.
+    pub fn set_group(&self, gid: Gid) -> Result<()> {
+        self.check_writable_mount()?;
+        let gid = match self.mount.idmap() {
+            Some(idmap) => idmap.map_gid_from_mount(gid)?,
+            None => gid,
+        };
+        self.inode().set_group(gid)
+    }
+
+    pub fn set_xattr(
+        &self,
+        name: XattrName,
+        value_reader: &mut VmReader,
+        flags: XattrSetFlags,
+    ) -> Result<()> {
+        self.check_writable_mount()?;
+        self.inode().set_xattr(name, value_reader, flags)
+    }
+
+    pub fn remove_xattr(&self, name: XattrName) -> Result<()> {
+        self.check_writable_mount()?;
+        self.inode().remove_xattr(name)
+    }
+}
+
 // Methods inherited from `Inode`.
 #[inherit_methods(from = "self.inode()")]
 impl Path {
     pub fn fs(&self) -> Arc<dyn FileSystem>;
     pub fn sync_all(&self) -> Result<()>;
     pub fn sync_data(&self) -> Result<()>;
-    pub fn metadata(&self) -> Metadata;
     pub fn mode(&self) -> Result<InodeMode>;
-    pub fn set_mode(&self, mode: InodeMode) -> Result<()>;
     pub fn size(&self) -> usize;
-    pub fn resize(&self, size: usize) -> Result<()>;
-    pub fn owner(&self) -> Result<Uid>;
-    pub fn set_owner(&self, uid: Uid) -> Result<()>;
-    pub fn group(&self) -> Result<Gid>;
-    pub fn set_group(&self, gid: Gid) -> Result<()>;
     pub fn atime(&self) -> Duration;
     pub fn set_atime(&self, time: Duration);
     pub fn mtime(&self) -> Duration;
     pub fn set_mtime(&self, time: Duration);
     pub fn ctime(&self) -> Duration;
     pub fn set_ctime(&self, time: Duration);
-    pub fn set_xattr(
-        &self,
-        name: XattrName,
-        value_reader: &mut VmReader,
-        flags: XattrSetFlags,
-    ) -> Result<()>;
     pub fn get_xattr(&self, name: XattrName, value_writer: &mut VmWriter) -> Result<usize>;
     pub fn list_xattr(
         &self,
         namespace: XattrNamespace,
         list_writer: &mut VmWriter,
     ) -> Result<usize>;
-    pub fn remove_xattr(&self, name: XattrName) -> Result<()>;
 }
 
 /// Checks if the file name is ".", indicating it's the current directory.
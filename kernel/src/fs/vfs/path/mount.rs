@@ -1,10 +1,14 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use core::sync::atomic::{AtomicU32, Ordering};
+use core::{
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicU32, Ordering},
+};
 
 use atomic_integer_wrapper::define_atomic_version_of_integer_like_type;
 use hashbrown::HashMap;
 use id_alloc::IdAlloc;
+use ostd::sync::{PreemptDisabled, Rcu, RwLockReadGuard, RwLockWriteGuard};
 use spin::Once;
 
 use crate::{
@@ -15,6 +19,7 @@ use crate::{
             path::{
                 Path,
                 dentry::{Dentry, DentryKey},
+                idmap::MountIdmap,
                 mount_namespace::MountNamespace,
             },
         },
@@ -32,14 +37,50 @@ pub enum MountPropType {
     /// do not propagate to or from the private mounts.
     #[default]
     Private,
-    // TODO: Implement other propagation types.
+    /// A shared mount belongs to a peer group. Mount and unmount events
+    /// propagate among all the mounts in the peer group.
+    Shared,
+    /// A slave mount receives mount and unmount events from its master peer
+    /// group, but does not propagate events to the master peer group.
+    Slave,
+    /// An unbindable mount is a private mount that cannot be bind mounted.
+    Unbindable,
+}
+
+/// A change to the attributes of mounts.
+///
+/// This is what the `mount_setattr` system call requests.
+#[derive(Debug)]
+pub struct MountAttrChange {
+    /// The flags to be set.
+    pub flags_to_set: PerMountFlags,
+    /// The flags to be cleared. They are cleared before `flags_to_set` are set.
+    pub flags_to_clear: PerMountFlags,
+    /// The new propagation type, if it should be changed.
+    pub propagation: Option<MountPropType>,
+    /// The ID mapping to be applied, if the mounts should become idmapped.
+    pub idmap: Option<Arc<MountIdmap>>,
 }
 
 static ID_ALLOCATOR: Once<SpinLock<IdAlloc>> = Once::new();
 
+static PEER_GROUP_ID_ALLOCATOR: Once<SpinLock<IdAlloc>> = Once::new();
+
+// This lock protects the relationships between mounts and peer groups, so that mount
+// propagation always sees a consistent view of the peer groups.
+static PROPAGATION_LOCK: Mutex<()> = Mutex::new(());
+
+// TODO: This lock is a workaround to guarantee the atomicity of the operations that modify
+// mount attributes. We need to re-design the lock mechanism of `Mount` and file system in
+// the future.
+static MOUNT_ATTR_LOCK: Mutex<()> = Mutex::new(());
+
 /// The reserved mount ID, which represents an invalid mount.
 static RESERVED_MOUNT_ID: usize = 0;
 
+/// The reserved peer group ID. Linux starts peer group IDs from 1.
+static RESERVED_PEER_GROUP_ID: usize = 0;
+
 pub(super) fn init() {
     // TODO: Make it configurable.
     const MAX_MOUNT_NUM: usize = 10000;
@@ -48,6 +89,92 @@ pub(super) fn init() {
     let _ = id_allocator.alloc_specific(RESERVED_MOUNT_ID).unwrap(); // Reserve mount ID 0.
 
     ID_ALLOCATOR.call_once(|| SpinLock::new(id_allocator));
+
+    // There cannot be more peer groups than mounts.
+    let mut peer_group_id_allocator = IdAlloc::with_capacity(MAX_MOUNT_NUM);
+    let _ = peer_group_id_allocator
+        .alloc_specific(RESERVED_PEER_GROUP_ID)
+        .unwrap();
+
+    PEER_GROUP_ID_ALLOCATOR.call_once(|| SpinLock::new(peer_group_id_allocator));
+}
+
+/// A peer group, which is a set of shared mounts that propagate events to each other.
+///
+/// A peer group may also have slave mounts, which receive the events that are
+/// propagated to the peer group.
+///
+/// Reference: <https://www.kernel.org/doc/Documentation/filesystems/sharedsubtree.txt>
+struct PeerGroup {
+    id: usize,
+    members: SpinLock<Vec<Weak<Mount>>>,
+    slaves: SpinLock<Vec<Weak<Mount>>>,
+}
+
+impl PeerGroup {
+    fn new() -> Arc<Self> {
+        let id = PEER_GROUP_ID_ALLOCATOR
+            .get()
+            .unwrap()
+            .lock()
+            .alloc()
+            .unwrap();
+
+        Arc::new(Self {
+            id,
+            members: SpinLock::new(Vec::new()),
+            slaves: SpinLock::new(Vec::new()),
+        })
+    }
+
+    /// Returns the mounts in this peer group.
+    fn members(&self) -> Vec<Arc<Mount>> {
+        self.members
+            .lock()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect()
+    }
+
+    /// Returns the slave mounts of this peer group.
+    fn slaves(&self) -> Vec<Arc<Mount>> {
+        self.slaves
+            .lock()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect()
+    }
+}
+
+impl Drop for PeerGroup {
+    fn drop(&mut self) {
+        PEER_GROUP_ID_ALLOCATOR.get().unwrap().lock().free(self.id);
+    }
+}
+
+/// Adds `mount` to `list` if it is not in the list.
+fn add_to_mount_list(list: &SpinLock<Vec<Weak<Mount>>>, mount: &Mount) {
+    let mut list = list.lock();
+    if !list.iter().any(|weak| core::ptr::eq(weak.as_ptr(), mount)) {
+        list.push(mount.this.clone());
+    }
+}
+
+/// Removes `mount` and all the dropped mounts from `list`.
+fn remove_from_mount_list(list: &SpinLock<Vec<Weak<Mount>>>, mount: &Mount) {
+    list.lock()
+        .retain(|weak| !core::ptr::eq(weak.as_ptr(), mount) && weak.strong_count() > 0);
+}
+
+/// The propagation state of a mount.
+#[derive(Clone, Default)]
+struct Propagation {
+    /// The peer group of the mount, if the mount is shared.
+    peer_group: Option<Arc<PeerGroup>>,
+    /// The peer group that the mount receives events from, if the mount is a slave.
+    master: Option<Arc<PeerGroup>>,
+    /// Whether the mount is unbindable.
+    is_unbindable: bool,
 }
 
 bitflags! {
@@ -60,6 +187,8 @@ bitflags! {
         const NODEV          = 1 << 2;
         /// Disallow program execution.
         const NOEXEC         = 1 << 3;
+        /// Do not follow symlinks.
+        const NOSYMFOLLOW    = 1 << 8;
         /// Do not update access times.
         const NOATIME        = 1 << 10;
         /// Do not update directory access times.
@@ -81,6 +210,11 @@ impl Default for PerMountFlags {
 }
 
 impl PerMountFlags {
+    /// The flags that select the atime policy.
+    ///
+    /// In Linux, `NOATIME`, `RELATIME`, and `STRICTATIME` are mutually exclusive.
+    pub const ATIME_MASK: Self = Self::NOATIME.union(Self::RELATIME).union(Self::STRICTATIME);
+
     /// Gets the atime policy.
     fn atime_policy(&self) -> AtimePolicy {
         if self.contains(PerMountFlags::STRICTATIME) {
@@ -119,6 +253,9 @@ impl core::fmt::Display for PerMountFlags {
         if self.contains(PerMountFlags::NOEXEC) {
             write!(f, ",noexec")?;
         }
+        if self.contains(PerMountFlags::NOSYMFOLLOW) {
+            write!(f, ",nosymfollow")?;
+        }
         if self.contains(PerMountFlags::NODIRATIME) {
             write!(f, ",nodiratime")?;
         }
@@ -175,13 +312,15 @@ pub struct Mount {
     /// The parent mount node.
     parent: RwLock<Option<Weak<Mount>>>,
     /// Child mount nodes which are mounted on one dentry of self.
-    pub(super) children: RwLock<HashMap<DentryKey, Arc<Self>>>,
+    pub(super) children: MountChildren,
     /// The associated mount namespace.
     mnt_ns: Weak<MountNamespace>,
-    /// Propagation type of this mount (e.g., private, shared).
-    propagation: RwLock<MountPropType>,
+    /// The propagation state of this mount (e.g., private, shared).
+    propagation: RwLock<Propagation>,
     /// The flags of this mount.
     flags: AtomicPerMountFlags,
+    /// The ID mapping of this mount, if this is an idmapped mount.
+    idmap: RwLock<Option<Arc<MountIdmap>>>,
     /// Reference to self.
     this: Weak<Self>,
 }
@@ -232,12 +371,13 @@ impl Mount {
             root_dentry: Dentry::new_root(fs.root_inode()),
             mountpoint: RwLock::new(None),
             parent: RwLock::new(parent_mount),
-            children: RwLock::new(HashMap::new()),
-            propagation: RwLock::new(MountPropType::default()),
+            children: MountChildren::new(),
+            propagation: RwLock::new(Propagation::default()),
             fs,
             source,
             mnt_ns,
             flags: AtomicPerMountFlags::new(flags),
+            idmap: RwLock::new(None),
             this: weak_self.clone(),
         })
     }
@@ -287,6 +427,9 @@ impl Mount {
         self.children.write().insert(key, child_mount.clone());
         child_mount.set_mountpoint(mountpoint);
 
+        let _guard = PROPAGATION_LOCK.lock();
+        self.propagate_mount(&child_mount, mountpoint);
+
         Ok(child_mount)
     }
 
@@ -302,6 +445,12 @@ impl Mount {
 
         child_mount.clear_mountpoint();
 
+        let _guard = PROPAGATION_LOCK.lock();
+        self.propagate_unmount(&child_mount, mountpoint);
+        for mount in child_mount.collect_subtree() {
+            mount.make_private();
+        }
+
         Ok(child_mount)
     }
 
@@ -312,24 +461,35 @@ impl Mount {
     ///
     /// If the `new_ns` is set, the new mount will belong to the given mount namespace.
     /// Otherwise, it will belong to the same mount namespace as the current mount.
+    ///
+    /// The new mount node will have the same propagation type as the original one.
+    /// In particular, a clone of a shared mount is a peer of the original mount.
     fn clone_mount(
         &self,
         root_dentry: &Arc<Dentry>,
         new_ns: Option<&Weak<MountNamespace>>,
     ) -> Arc<Self> {
-        Arc::new_cyclic(|weak_self| Self {
+        let new_mount = Arc::new_cyclic(|weak_self| Self {
             id: ID_ALLOCATOR.get().unwrap().lock().alloc().unwrap(),
             root_dentry: root_dentry.clone(),
             mountpoint: RwLock::new(None),
             parent: RwLock::new(None),
-            children: RwLock::new(HashMap::new()),
-            propagation: RwLock::new(MountPropType::default()),
+            children: MountChildren::new(),
+            propagation: RwLock::new(Propagation::default()),
             fs: self.fs.clone(),
             source: self.source.clone(),
             mnt_ns: new_ns.cloned().unwrap_or_else(|| self.mnt_ns.clone()),
             flags: AtomicPerMountFlags::new(self.flags.load(Ordering::Relaxed)),
+            idmap: RwLock::new(self.idmap.read().clone()),
             this: weak_self.clone(),
-        })
+        });
+
+        let propagation = self.propagation.read().clone();
+        new_mount.set_peer_group(propagation.peer_group.as_ref());
+        new_mount.set_master(propagation.master.as_ref());
+        new_mount.propagation.write().is_unbindable = propagation.is_unbindable;
+
+        new_mount
     }
 
     /// Clones a mount tree starting from the specified root `Dentry`.
@@ -342,12 +502,24 @@ impl Mount {
     /// Otherwise, only the root mount node will be copied.
     ///
     /// If the `new_ns` is set, the new mount tree will belong to the given mount namespace.
-    /// Otherwise, it will belong to the same mount namespace as the current mount.
+    /// Otherwise, it will belong to the same mount namespace as the current mount. In the
+    /// latter case, the tree is cloned for a bind mount, so unbindable mounts are skipped.
     pub(super) fn clone_mount_tree(
         &self,
         root_dentry: &Arc<Dentry>,
         new_ns: Option<&Weak<MountNamespace>>,
         recursive: bool,
+    ) -> Arc<Self> {
+        let _guard = PROPAGATION_LOCK.lock();
+        self.copy_mount_tree(root_dentry, new_ns, recursive)
+    }
+
+    /// Clones a mount tree like [`Self::clone_mount_tree`], with `PROPAGATION_LOCK` held.
+    fn copy_mount_tree(
+        &self,
+        root_dentry: &Arc<Dentry>,
+        new_ns: Option<&Weak<MountNamespace>>,
+        recursive: bool,
     ) -> Arc<Self> {
         let new_root_mount = self.clone_mount(root_dentry, new_ns);
         if !recursive {
@@ -364,6 +536,9 @@ impl Mount {
                 if !mountpoint.is_equal_or_descendant_of(new_parent_mount.root_dentry()) {
                     continue;
                 }
+                if new_ns.is_none() && old_child_mount.is_unbindable() {
+                    continue;
+                }
                 let new_child_mount =
                     old_child_mount.clone_mount(old_child_mount.root_dentry(), new_ns);
                 let key = mountpoint.key();
@@ -383,18 +558,267 @@ impl Mount {
 
     /// Sets the propagation type of this mount.
     pub(super) fn set_propagation(&self, prop: MountPropType, recursive: bool) {
-        *self.propagation.write() = prop;
+        let _guard = PROPAGATION_LOCK.lock();
+
         if !recursive {
+            self.change_propagation(prop);
             return;
         }
 
-        let mut worklist: VecDeque<Arc<Mount>> = self.children.read().values().cloned().collect();
-        while let Some(mount) = worklist.pop_front() {
-            *mount.propagation.write() = prop;
-            worklist.extend(mount.children.read().values().cloned());
+        for mount in self.collect_subtree() {
+            mount.change_propagation(prop);
         }
     }
 
+    /// Changes the propagation type of this mount, with `PROPAGATION_LOCK` held.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.16/source/fs/pnode.c#L125>
+    fn change_propagation(&self, prop: MountPropType) {
+        match prop {
+            MountPropType::Shared => {
+                if self.propagation.read().peer_group.is_none() {
+                    self.set_peer_group(Some(&PeerGroup::new()));
+                }
+                self.propagation.write().is_unbindable = false;
+            }
+            MountPropType::Private => self.make_private(),
+            MountPropType::Slave => {
+                self.make_slave();
+                self.propagation.write().is_unbindable = false;
+            }
+            MountPropType::Unbindable => {
+                self.make_private();
+                self.propagation.write().is_unbindable = true;
+            }
+        }
+    }
+
+    /// Makes this mount a private mount, with `PROPAGATION_LOCK` held.
+    fn make_private(&self) {
+        let master = self.propagation.read().master.clone();
+        self.leave_peer_group(master.as_ref());
+        self.set_master(None);
+        self.propagation.write().is_unbindable = false;
+    }
+
+    /// Makes this mount a slave mount, with `PROPAGATION_LOCK` held.
+    ///
+    /// If this mount is shared and has other peers, the peer group becomes the master
+    /// of this mount. Otherwise, this mount keeps its current master, if any.
+    fn make_slave(&self) {
+        let master = self.propagation.read().master.clone();
+        let Some(peer_group) = self.leave_peer_group(master.as_ref()) else {
+            return;
+        };
+
+        if !peer_group.members().is_empty() {
+            self.set_master(Some(&peer_group));
+        }
+    }
+
+    /// Leaves the peer group of this mount and returns the peer group.
+    ///
+    /// If this mount is the last member of the peer group, the slaves of the peer
+    /// group will be transferred to `new_master`.
+    fn leave_peer_group(&self, new_master: Option<&Arc<PeerGroup>>) -> Option<Arc<PeerGroup>> {
+        let peer_group = self.propagation.write().peer_group.take()?;
+        remove_from_mount_list(&peer_group.members, self);
+
+        if peer_group.members().is_empty() {
+            for slave in peer_group.slaves() {
+                slave.set_master(new_master);
+            }
+        }
+
+        Some(peer_group)
+    }
+
+    /// Sets the peer group of this mount, with `PROPAGATION_LOCK` held.
+    fn set_peer_group(&self, peer_group: Option<&Arc<PeerGroup>>) {
+        let mut propagation = self.propagation.write();
+        if let Some(old_peer_group) = propagation.peer_group.take() {
+            remove_from_mount_list(&old_peer_group.members, self);
+        }
+        if let Some(peer_group) = peer_group {
+            add_to_mount_list(&peer_group.members, self);
+        }
+        propagation.peer_group = peer_group.cloned();
+    }
+
+    /// Sets the master peer group of this mount, with `PROPAGATION_LOCK` held.
+    fn set_master(&self, master: Option<&Arc<PeerGroup>>) {
+        let mut propagation = self.propagation.write();
+        if let Some(old_master) = propagation.master.take() {
+            remove_from_mount_list(&old_master.slaves, self);
+        }
+        if let Some(master) = master {
+            add_to_mount_list(&master.slaves, self);
+        }
+        propagation.master = master.cloned();
+    }
+
+    /// Propagates the mount event of `child_mount` on `mountpoint` of this mount.
+    ///
+    /// A copy of `child_mount` is mounted on the corresponding mountpoint of every
+    /// mount that receives events from this mount. The copies on the peers of this
+    /// mount become peers of `child_mount`, while the copies on the slaves become
+    /// slaves of `child_mount`.
+    ///
+    /// This method must be called with `PROPAGATION_LOCK` held.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.16/source/fs/pnode.c#L322>
+    fn propagate_mount(self: &Arc<Self>, child_mount: &Arc<Self>, mountpoint: &Arc<Dentry>) {
+        let Some(peer_group) = self.propagation.read().peer_group.clone() else {
+            return;
+        };
+
+        // The mounts that are mounted on a shared mount become shared as well.
+        for mount in child_mount.collect_subtree() {
+            if mount.propagation.read().peer_group.is_none() {
+                mount.set_peer_group(Some(&PeerGroup::new()));
+            }
+        }
+        let child_peer_group = child_mount.propagation.read().peer_group.clone().unwrap();
+
+        // Each work item consists of a peer group that receives the event, the peer group
+        // of the copies, and the master of the copies.
+        let mut worklist = VecDeque::from([(peer_group.clone(), child_peer_group, None)]);
+        let mut visited = vec![peer_group];
+        while let Some((peer_group, copy_peer_group, copy_master)) = worklist.pop_front() {
+            for member in peer_group.members() {
+                if !Arc::ptr_eq(&member, self) {
+                    member.mount_copy(
+                        child_mount,
+                        mountpoint,
+                        Some(&copy_peer_group),
+                        copy_master.as_ref(),
+                    );
+                }
+            }
+
+            for slave in peer_group.slaves() {
+                let slave_peer_group = slave.propagation.read().peer_group.clone();
+                let Some(slave_peer_group) = slave_peer_group else {
+                    slave.mount_copy(child_mount, mountpoint, None, Some(&copy_peer_group));
+                    continue;
+                };
+
+                if visited
+                    .iter()
+                    .any(|group| Arc::ptr_eq(group, &slave_peer_group))
+                {
+                    continue;
+                }
+                visited.push(slave_peer_group.clone());
+                worklist.push_back((
+                    slave_peer_group,
+                    PeerGroup::new(),
+                    Some(copy_peer_group.clone()),
+                ));
+            }
+        }
+    }
+
+    /// Mounts a copy of `mount` on `mountpoint` of this mount as a result of
+    /// mount propagation.
+    ///
+    /// The copy will belong to `peer_group` and receive events from `master`.
+    fn mount_copy(
+        self: &Arc<Self>,
+        mount: &Arc<Self>,
+        mountpoint: &Arc<Dentry>,
+        peer_group: Option<&Arc<PeerGroup>>,
+        master: Option<&Arc<PeerGroup>>,
+    ) {
+        // The mountpoint is not visible in this mount.
+        if !mountpoint.is_equal_or_descendant_of(self.root_dentry()) {
+            return;
+        }
+
+        let is_peer = {
+            let propagation = mount.propagation.read();
+            peer_group.zip(propagation.peer_group.as_ref()).is_some_and(
+                |(peer_group, mount_peer_group)| Arc::ptr_eq(peer_group, mount_peer_group),
+            )
+        };
+
+        let new_mount = mount.copy_mount_tree(mount.root_dentry(), Some(self.mnt_ns()), true);
+        if !is_peer {
+            // The copies of the descendant mounts are slaves of the original mounts.
+            for descendant in new_mount.collect_subtree().into_iter().skip(1) {
+                descendant.make_slave();
+            }
+        }
+        new_mount.set_peer_group(peer_group);
+        new_mount.set_master(master);
+
+        self.children
+            .write()
+            .insert(mountpoint.key(), new_mount.clone());
+        new_mount.set_parent(Some(self));
+        new_mount.set_mountpoint(mountpoint);
+    }
+
+    /// Propagates the unmount event of `child_mount` on `mountpoint` of this mount.
+    ///
+    /// The copies of `child_mount` on the mounts that receive events from this mount
+    /// are unmounted as well, unless they have child mounts.
+    ///
+    /// This method must be called with `PROPAGATION_LOCK` held.
+    fn propagate_unmount(&self, child_mount: &Self, mountpoint: &Dentry) {
+        let key = mountpoint.key();
+        for receiver in self.collect_receivers() {
+            let mut children = receiver.children.write();
+            let Some(copy) = children.get(&key) else {
+                continue;
+            };
+            if !Arc::ptr_eq(copy.root_dentry(), child_mount.root_dentry())
+                || !copy.children.read().is_empty()
+            {
+                continue;
+            }
+
+            let copy = children.remove(&key).unwrap();
+            drop(children);
+            copy.clear_mountpoint();
+            copy.make_private();
+        }
+    }
+
+    /// Collects the mounts that receive the events propagated from this mount.
+    ///
+    /// This method must be called with `PROPAGATION_LOCK` held.
+    fn collect_receivers(&self) -> Vec<Arc<Self>> {
+        let Some(peer_group) = self.propagation.read().peer_group.clone() else {
+            return Vec::new();
+        };
+
+        let mut receivers = Vec::new();
+        let mut worklist = VecDeque::from([peer_group.clone()]);
+        let mut visited = vec![peer_group];
+        while let Some(peer_group) = worklist.pop_front() {
+            let members = peer_group.members().into_iter();
+            receivers.extend(members.filter(|member| !core::ptr::eq(member.as_ref(), self)));
+
+            for slave in peer_group.slaves() {
+                let slave_peer_group = slave.propagation.read().peer_group.clone();
+                match slave_peer_group {
+                    Some(slave_peer_group)
+                        if visited
+                            .iter()
+                            .any(|group| Arc::ptr_eq(group, &slave_peer_group)) => {}
+                    Some(slave_peer_group) => {
+                        visited.push(slave_peer_group.clone());
+                        worklist.push_back(slave_peer_group);
+                    }
+                    None => receivers.push(slave),
+                }
+            }
+        }
+
+        receivers
+    }
+
     /// Detaches the mount node from the parent mount node.
     pub(super) fn detach_from_parent(&self) {
         if let Some(parent) = self.parent() {
@@ -423,14 +847,21 @@ impl Mount {
     }
 
     /// Grafts the mount node tree to the mountpoint.
+    ///
+    /// If the mount of the mountpoint is shared, the mount event is propagated.
     pub(super) fn graft_mount_tree(&self, target_path: &Path) {
         self.detach_from_parent();
         self.attach_to_path(target_path);
+
+        let _guard = PROPAGATION_LOCK.lock();
+        target_path
+            .mount_node()
+            .propagate_mount(&self.this(), &target_path.dentry);
     }
 
     /// Gets a child mount node from the mountpoint if any.
     pub(super) fn get(&self, mountpoint: &Dentry) -> Option<Arc<Self>> {
-        self.children.read().get(&mountpoint.key()).cloned()
+        self.children.get(&mountpoint.key())
     }
 
     /// Gets the root `Dentry` of this mount node.
@@ -477,24 +908,17 @@ impl Mount {
         data: Option<CString>,
         ctx: &Context,
     ) -> Result<()> {
-        // TODO: This lock is a workaround to guarantee the atomicity of remount operation.
-        // We need to re-design the lock mechanism of `Mount` and file system in the future.
-        static REMOUNT_LOCK: Mutex<()> = Mutex::new(());
-
-        let _guard = REMOUNT_LOCK.lock();
+        let _guard = MOUNT_ATTR_LOCK.lock();
 
         if let Some(flags) = fs_flags {
             self.fs.set_fs_flags(flags, data, ctx)?;
         }
 
         // The logics here are consistent with Linux.
-        // In Linux, `NOATIME`, `RELATIME`, and `STRICTATIME` are mutually exclusive.
-        // If none of them nor `NODIRATIME` are set, the atime policy will be inherited
-        // from the old flags.
+        // If none of the atime flags nor `NODIRATIME` are set, the atime policy will be
+        // inherited from the old flags.
         // Reference: https://elixir.bootlin.com/linux/v6.17/source/fs/namespace.c#L4097
-        const ATIME_MASK: PerMountFlags = PerMountFlags::NOATIME
-            .union(PerMountFlags::RELATIME)
-            .union(PerMountFlags::STRICTATIME);
+        const ATIME_MASK: PerMountFlags = PerMountFlags::ATIME_MASK;
 
         let need_inherit_atime = !mount_flags.intersects(ATIME_MASK | PerMountFlags::NODIRATIME);
 
@@ -509,6 +933,93 @@ impl Mount {
         Ok(())
     }
 
+    /// Changes the attributes of this mount, or of the whole mount subtree if `recursive`
+    /// is true.
+    ///
+    /// The change is applied either to all the mounts or to none of them.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EPERM` if an ID mapping is requested while one of the mounts is
+    /// already idmapped.
+    pub(super) fn set_attr(&self, attr: &MountAttrChange, recursive: bool) -> Result<()> {
+        let _guard = MOUNT_ATTR_LOCK.lock();
+
+        let mounts = if recursive {
+            self.collect_subtree()
+        } else {
+            vec![self.this()]
+        };
+
+        if attr.idmap.is_some() && mounts.iter().any(|mount| mount.idmap().is_some()) {
+            return_errno_with_message!(Errno::EPERM, "the mount is already idmapped");
+        }
+
+        for mount in mounts.iter() {
+            let old_flags = mount.flags.load(Ordering::Relaxed);
+            let new_flags = (old_flags - attr.flags_to_clear) | attr.flags_to_set;
+            mount.flags.store(new_flags, Ordering::Relaxed);
+
+            if let Some(idmap) = attr.idmap.as_ref() {
+                *mount.idmap.write() = Some(idmap.clone());
+            }
+        }
+
+        if let Some(propagation) = attr.propagation {
+            let _propagation_guard = PROPAGATION_LOCK.lock();
+            for mount in mounts.iter() {
+                mount.change_propagation(propagation);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Collects this mount and all of its descendant mounts.
+    fn collect_subtree(&self) -> Vec<Arc<Self>> {
+        let mut mounts = vec![self.this()];
+        let mut index = 0;
+        while index < mounts.len() {
+            let children: Vec<_> = mounts[index].children.read().values().cloned().collect();
+            mounts.extend(children);
+            index += 1;
+        }
+
+        mounts
+    }
+
+    /// Returns the ID of the peer group of this mount if this mount is shared.
+    pub(in crate::fs) fn peer_group_id(&self) -> Option<usize> {
+        let propagation = self.propagation.read();
+        propagation
+            .peer_group
+            .as_ref()
+            .map(|peer_group| peer_group.id)
+    }
+
+    /// Returns the ID of the master peer group of this mount if this mount is a slave.
+    pub(in crate::fs) fn master_id(&self) -> Option<usize> {
+        let propagation = self.propagation.read();
+        propagation.master.as_ref().map(|master| master.id)
+    }
+
+    /// Returns whether this mount is unbindable.
+    pub(in crate::fs) fn is_unbindable(&self) -> bool {
+        self.propagation.read().is_unbindable
+    }
+
+    /// Returns whether this mount or any of its descendant mounts is unbindable.
+    pub(super) fn contains_unbindable(&self) -> bool {
+        self.collect_subtree()
+            .iter()
+            .any(|mount| mount.is_unbindable())
+    }
+
+    /// Returns the ID mapping of this mount if this is an idmapped mount.
+    pub(in crate::fs) fn idmap(&self) -> Option<Arc<MountIdmap>> {
+        self.idmap.read().clone()
+    }
+
     /// Gets the parent mount node if any.
     pub(in crate::fs) fn parent(&self) -> Option<Weak<Self>> {
         self.parent.read().as_ref().cloned()
@@ -604,3 +1115,75 @@ impl Drop for Mount {
         ID_ALLOCATOR.get().unwrap().lock().free(self.id);
     }
 }
+
+/// The child mount nodes of a mount node, keyed by their mountpoints.
+///
+/// The children are looked up whenever a path walk crosses a directory, so the lookups via
+/// [`Self::get`] do not take the lock. Instead, they read a copy of the children that is
+/// published via RCU when a write guard is dropped. The copy holds weak references, so that the
+/// last reference to a mount node is never dropped in the RCU callbacks.
+pub(super) struct MountChildren {
+    children: RwLock<HashMap<DentryKey, Arc<Mount>>>,
+    view: Rcu<Box<HashMap<DentryKey, Weak<Mount>>>>,
+}
+
+impl MountChildren {
+    fn new() -> Self {
+        Self {
+            children: RwLock::new(HashMap::new()),
+            view: Rcu::new(Box::new(HashMap::new())),
+        }
+    }
+
+    /// Gets a child mount node from the key of the mountpoint without locking.
+    fn get(&self, key: &DentryKey) -> Option<Arc<Mount>> {
+        self.view.read().get().get(key).and_then(Weak::upgrade)
+    }
+
+    /// Locks the children for reading.
+    pub(super) fn read(
+        &self,
+    ) -> RwLockReadGuard<'_, HashMap<DentryKey, Arc<Mount>>, PreemptDisabled> {
+        self.children.read()
+    }
+
+    /// Locks the children for writing.
+    ///
+    /// The changes are published to the lockless lookups when the guard is dropped.
+    pub(super) fn write(&self) -> MountChildrenWriteGuard<'_> {
+        MountChildrenWriteGuard {
+            children: self.children.write(),
+            view: &self.view,
+        }
+    }
+}
+
+pub(super) struct MountChildrenWriteGuard<'a> {
+    children: RwLockWriteGuard<'a, HashMap<DentryKey, Arc<Mount>>, PreemptDisabled>,
+    view: &'a Rcu<Box<HashMap<DentryKey, Weak<Mount>>>>,
+}
+
+impl Deref for MountChildrenWriteGuard<'_> {
+    type Target = HashMap<DentryKey, Arc<Mount>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.children
+    }
+}
+
+impl DerefMut for MountChildrenWriteGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.children
+    }
+}
+
+impl Drop for MountChildrenWriteGuard<'_> {
+    fn drop(&mut self) {
+        let view = self
+            .children
+            .iter()
+            .map(|(key, child)| (key.clone(), Arc::downgrade(child)))
+            .collect();
+        self.view.update(Box::new(view));
+    }
+}
@@ -6,7 +6,10 @@ use crate::{
     fs::{
         fs_impls::ramfs::RamFs,
         pseudofs::{NsCommonOps, NsType, StashedDentry},
-        vfs::path::{Mount, Path, PathResolver},
+        vfs::{
+            file_system::FileSystem,
+            path::{Mount, MountPropType, Path, PathResolver},
+        },
     },
     prelude::*,
     process::{UserNamespace, credentials::capabilities::CapSet, posix_thread::PosixThread},
@@ -115,6 +118,29 @@ impl MountNamespace {
         Ok(())
     }
 
+    /// Returns all mounted filesystems in this mount namespace.
+    ///
+    /// A filesystem that is mounted multiple times is only returned once.
+    pub fn filesystems(&self) -> Vec<Arc<dyn FileSystem>> {
+        let mut mount_queue = VecDeque::new();
+        let mut filesystems: Vec<Arc<dyn FileSystem>> = Vec::new();
+        mount_queue.push_back(self.root.clone());
+
+        while let Some(current_mount) = mount_queue.pop_front() {
+            let fs = current_mount.fs();
+            if !filesystems.iter().any(|other| Arc::ptr_eq(other, fs)) {
+                filesystems.push(fs.clone());
+            }
+
+            let children = current_mount.children.read();
+            for child_mount in children.values() {
+                mount_queue.push_back(child_mount.clone());
+            }
+        }
+
+        filesystems
+    }
+
     /// Checks whether a given mount belongs to this mount namespace.
     pub fn owns(self: &Arc<Self>, mount: &Mount) -> bool {
         mount.mnt_ns().as_ptr() == Arc::as_ptr(self)
@@ -129,6 +155,7 @@ impl Drop for MountNamespace {
         let mut worklist = VecDeque::new();
         worklist.push_back(self.root.clone());
         while let Some(current_mount) = worklist.pop_front() {
+            current_mount.set_propagation(MountPropType::Private, false);
             let mut children = current_mount.children.write();
             for (_, child) in children.drain() {
                 child.set_parent(None);
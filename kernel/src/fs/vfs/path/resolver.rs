@@ -4,7 +4,7 @@ use alloc::str;
 
 use ostd::task::Task;
 
-use super::{Mount, Path};
+use super::{Mount, Path, PerMountFlags};
 use crate::{
     fs::{
         file::{
@@ -255,12 +255,6 @@ impl PathResolver {
             );
         }
 
-        // TODO: Check the following once we support `MS_SHARED`:
-        // "The propagation type of the parent mount of `new_root` and the
-        // parent mount of the current root directory must not be
-        // `MS_SHARED`; similarly, if `put_old` is an existing mount point,
-        // its propagation type must not be `MS_SHARED`."
-
         let current_ns_proxy = ctx.thread_local.borrow_ns_proxy();
         let current_mnt_ns = current_ns_proxy.unwrap().mnt_ns();
         if !current_mnt_ns.owns(&new_root_path.mount) || !current_mnt_ns.owns(&put_old_path.mount) {
@@ -290,6 +284,21 @@ impl PathResolver {
                 "`new_root` or the current root is on the rootfs mount"
             );
         }
+        // "The propagation type of the parent mount of `new_root` and the
+        // parent mount of the current root directory must not be
+        // `MS_SHARED`; similarly, if `put_old` is an existing mount point,
+        // its propagation type must not be `MS_SHARED`."
+        let new_root_parent = new_root_path.mount.parent().unwrap().upgrade().unwrap();
+        let root_parent = self.root.mount.parent().unwrap().upgrade().unwrap();
+        if new_root_parent.peer_group_id().is_some()
+            || root_parent.peer_group_id().is_some()
+            || (put_old_path.is_mount_root() && put_old_path.mount.peer_group_id().is_some())
+        {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "the propagation type of a related mount is shared"
+            );
+        }
         if !put_old_path.is_reachable_from(&new_root_path) {
             return_errno_with_message!(
                 Errno::EINVAL,
@@ -416,7 +425,7 @@ impl PathResolver {
     pub fn lookup_at_path(&self, path: &Path, name: &str) -> Result<Path> {
         let dir_dentry = path.dentry.as_dir_dentry_or_err()?;
 
-        if path.inode().check_permission(Permission::MAY_EXEC).is_err() {
+        if path.check_permission(Permission::MAY_EXEC).is_err() {
             return_errno_with_message!(Errno::EACCES, "the path cannot be looked up");
         }
         if name.len() > NAME_MAX {
@@ -571,6 +580,12 @@ impl PathResolver {
                 if follows >= SYMLINKS_MAX {
                     return_errno_with_message!(Errno::ELOOP, "there are too many symlinks");
                 }
+                if next_path.mount_flags().contains(PerMountFlags::NOSYMFOLLOW) {
+                    return_errno_with_message!(
+                        Errno::ELOOP,
+                        "the mount disallows following symlinks"
+                    );
+                }
                 let read_link_res = next_path.inode().read_link()?;
                 match read_link_res {
                     SymbolicLink::Plain(mut tmp_link_path) => {
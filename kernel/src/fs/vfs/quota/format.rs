@@ -0,0 +1,422 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The formats of quota files.
+//!
+//! A quota file starts with a header and the information of the quotas, which are
+//! followed by a radix tree of 1024-byte blocks. The tree has four levels, each of
+//! which is indexed by a byte of the ID, and its leaves refer to the data blocks that
+//! store the quota entries.
+//!
+//! Reference: <https://elixir.bootlin.com/linux/v6.18/source/fs/quota/quotaio_v2.h>
+
+use core::mem::offset_of;
+
+use super::{DiskQuota, QuotaInfo, QuotaType};
+use crate::{fs::vfs::inode::Inode, prelude::*};
+
+/// The size of the blocks in quota files.
+const QT_BLKSIZE: usize = 1024;
+/// The block of the root of the tree.
+const QT_TREEOFF: u32 = 1;
+/// The depth of the tree.
+const QT_TREEDEPTH: usize = 4;
+/// The number of references in a tree block.
+const QT_REFS_PER_BLOCK: usize = QT_BLKSIZE / size_of::<u32>();
+
+/// The magic numbers of quota files, indexed by the quota types.
+const V2_MAGICS: [u32; 3] = [0xd9c01f11, 0xd9c01927, 0xd9c03f14];
+
+/// The offset of the quota information.
+const V2_DQINFOOFF: usize = size_of::<RawHeader>();
+
+/// The size of the quota blocks, in which the space limits are stored.
+pub(super) const QUOTABLOCK_SIZE: u64 = 1024;
+
+/// The format of a quota file.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+pub enum QuotaFormat {
+    /// The tree format with 32-bit limits.
+    VfsV0 = 2,
+    /// The tree format with 64-bit limits.
+    VfsV1 = 4,
+}
+
+impl QuotaFormat {
+    fn version(&self) -> u32 {
+        match self {
+            Self::VfsV0 => 0,
+            Self::VfsV1 => 1,
+        }
+    }
+
+    fn entry_size(&self) -> usize {
+        match self {
+            Self::VfsV0 => size_of::<RawDquotV0>(),
+            Self::VfsV1 => size_of::<RawDquotV1>(),
+        }
+    }
+
+    fn entries_per_block(&self) -> usize {
+        (QT_BLKSIZE - size_of::<RawDataHeader>()) / self.entry_size()
+    }
+
+    /// Returns the maximum space limit in bytes.
+    pub(super) fn max_space_limit(&self) -> u64 {
+        match self {
+            Self::VfsV0 => (u32::MAX as u64) * QUOTABLOCK_SIZE,
+            Self::VfsV1 => i64::MAX as u64,
+        }
+    }
+
+    /// Returns the maximum inode limit.
+    pub(super) fn max_inode_limit(&self) -> u64 {
+        match self {
+            Self::VfsV0 => u32::MAX as u64,
+            Self::VfsV1 => i64::MAX as u64,
+        }
+    }
+
+    fn parse_entry(&self, bytes: &[u8]) -> (u32, DiskQuota) {
+        let (id, mut dquot) = match self {
+            Self::VfsV0 => {
+                let raw = RawDquotV0::from_bytes(bytes);
+                let dquot = DiskQuota {
+                    bhardlimit: raw.bhardlimit as u64 * QUOTABLOCK_SIZE,
+                    bsoftlimit: raw.bsoftlimit as u64 * QUOTABLOCK_SIZE,
+                    curspace: raw.curspace,
+                    ihardlimit: raw.ihardlimit as u64,
+                    isoftlimit: raw.isoftlimit as u64,
+                    curinodes: raw.curinodes as u64,
+                    btime: raw.btime,
+                    itime: raw.itime,
+                };
+                (raw.id, dquot)
+            }
+            Self::VfsV1 => {
+                let raw = RawDquotV1::from_bytes(bytes);
+                let dquot = DiskQuota {
+                    bhardlimit: raw.bhardlimit.saturating_mul(QUOTABLOCK_SIZE),
+                    bsoftlimit: raw.bsoftlimit.saturating_mul(QUOTABLOCK_SIZE),
+                    curspace: raw.curspace,
+                    ihardlimit: raw.ihardlimit,
+                    isoftlimit: raw.isoftlimit,
+                    curinodes: raw.curinodes,
+                    btime: raw.btime,
+                    itime: raw.itime,
+                };
+                (raw.id, dquot)
+            }
+        };
+
+        // Linux stores an all-zero entry with `itime` of one, since all-zero entries
+        // are considered unused.
+        if dquot.itime == 1 && (DiskQuota { itime: 0, ..dquot }).is_empty() {
+            dquot.itime = 0;
+        }
+        (id, dquot)
+    }
+
+    fn write_entry(&self, id: u32, dquot: &DiskQuota, bytes: &mut [u8]) {
+        match self {
+            Self::VfsV0 => {
+                let raw = RawDquotV0 {
+                    id,
+                    ihardlimit: dquot.ihardlimit as u32,
+                    isoftlimit: dquot.isoftlimit as u32,
+                    curinodes: dquot.curinodes as u32,
+                    bhardlimit: dquot.bhardlimit.div_ceil(QUOTABLOCK_SIZE) as u32,
+                    bsoftlimit: dquot.bsoftlimit.div_ceil(QUOTABLOCK_SIZE) as u32,
+                    curspace: dquot.curspace,
+                    btime: dquot.btime,
+                    itime: dquot.itime,
+                };
+                bytes.copy_from_slice(raw.as_bytes());
+            }
+            Self::VfsV1 => {
+                let raw = RawDquotV1 {
+                    id,
+                    pad: 0,
+                    ihardlimit: dquot.ihardlimit,
+                    isoftlimit: dquot.isoftlimit,
+                    curinodes: dquot.curinodes,
+                    bhardlimit: dquot.bhardlimit.div_ceil(QUOTABLOCK_SIZE),
+                    bsoftlimit: dquot.bsoftlimit.div_ceil(QUOTABLOCK_SIZE),
+                    curspace: dquot.curspace,
+                    btime: dquot.btime,
+                    itime: dquot.itime,
+                };
+                bytes.copy_from_slice(raw.as_bytes());
+            }
+        }
+    }
+}
+
+/// The header of a quota file.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct RawHeader {
+    magic: u32,
+    version: u32,
+}
+
+/// The information of the quotas in a quota file.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct RawInfo {
+    /// The grace time of the space in seconds.
+    bgrace: u32,
+    /// The grace time of the inodes in seconds.
+    igrace: u32,
+    flags: u32,
+    /// The number of blocks in the file.
+    blocks: u32,
+    /// The first free block.
+    free_blk: u32,
+    /// The first data block that has free entries.
+    free_entry: u32,
+}
+
+/// The header of a data block.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct RawDataHeader {
+    next_free: u32,
+    prev_free: u32,
+    /// The number of the used entries in the block.
+    entries: u16,
+    pad1: u16,
+    pad2: u32,
+}
+
+/// A quota entry of `QuotaFormat::VfsV0`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct RawDquotV0 {
+    id: u32,
+    ihardlimit: u32,
+    isoftlimit: u32,
+    curinodes: u32,
+    /// The hard limit of the space in quota blocks.
+    bhardlimit: u32,
+    /// The soft limit of the space in quota blocks.
+    bsoftlimit: u32,
+    /// The used space in bytes.
+    curspace: u64,
+    btime: u64,
+    itime: u64,
+}
+
+/// A quota entry of `QuotaFormat::VfsV1`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct RawDquotV1 {
+    id: u32,
+    pad: u32,
+    ihardlimit: u64,
+    isoftlimit: u64,
+    curinodes: u64,
+    /// The hard limit of the space in quota blocks.
+    bhardlimit: u64,
+    /// The soft limit of the space in quota blocks.
+    bsoftlimit: u64,
+    /// The used space in bytes.
+    curspace: u64,
+    btime: u64,
+    itime: u64,
+}
+
+/// Loads the quota information and entries from a quota file.
+pub(super) fn load(
+    file: &dyn Inode,
+    type_: QuotaType,
+    format: QuotaFormat,
+) -> Result<(QuotaInfo, BTreeMap<u32, DiskQuota>)> {
+    let mut first_block = [0u8; QT_BLKSIZE];
+    read_block(file, 0, &mut first_block)?;
+
+    let header = RawHeader::from_first_bytes(&first_block);
+    if header.magic != V2_MAGICS[type_ as usize] || header.version != format.version() {
+        return_errno_with_message!(Errno::EINVAL, "the quota file has an invalid header");
+    }
+    let raw_info = RawInfo::from_first_bytes(&first_block[V2_DQINFOOFF..]);
+    let info = QuotaInfo {
+        bgrace: raw_info.bgrace as u64,
+        igrace: raw_info.igrace as u64,
+        flags: raw_info.flags,
+    };
+
+    let mut data_blocks = BTreeSet::new();
+    collect_data_blocks(file, QT_TREEOFF, 0, raw_info.blocks, &mut data_blocks)?;
+
+    let mut dquots = BTreeMap::new();
+    let mut block = [0u8; QT_BLKSIZE];
+    let entry_size = format.entry_size();
+    for blk in data_blocks {
+        read_block(file, blk, &mut block)?;
+        let entries = &block[size_of::<RawDataHeader>()..];
+        for entry in entries
+            .chunks_exact(entry_size)
+            .take(format.entries_per_block())
+        {
+            if entry.iter().all(|byte| *byte == 0) {
+                continue;
+            }
+            let (id, dquot) = format.parse_entry(entry);
+            dquots.insert(id, dquot);
+        }
+    }
+
+    Ok((info, dquots))
+}
+
+/// Collects the data blocks that are referred by the tree block `blk` at `depth`.
+fn collect_data_blocks(
+    file: &dyn Inode,
+    blk: u32,
+    depth: usize,
+    nr_blocks: u32,
+    data_blocks: &mut BTreeSet<u32>,
+) -> Result<()> {
+    let mut block = [0u8; QT_BLKSIZE];
+    read_block(file, blk, &mut block)?;
+
+    for raw_ref in block.chunks_exact(size_of::<u32>()) {
+        let child = u32::from_le_bytes(raw_ref.try_into().unwrap());
+        if child == 0 {
+            continue;
+        }
+        if child >= nr_blocks {
+            return_errno_with_message!(Errno::EINVAL, "the quota file has an invalid reference");
+        }
+
+        if depth == QT_TREEDEPTH - 1 {
+            data_blocks.insert(child);
+        } else {
+            collect_data_blocks(file, child, depth + 1, nr_blocks, data_blocks)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn read_block(file: &dyn Inode, blk: u32, buf: &mut [u8; QT_BLKSIZE]) -> Result<()> {
+    let len = file.read_bytes_at(blk as usize * QT_BLKSIZE, buf)?;
+    if len != QT_BLKSIZE {
+        return_errno_with_message!(Errno::EINVAL, "the quota file is truncated");
+    }
+    Ok(())
+}
+
+/// Stores the quota information and entries to a quota file.
+///
+/// The file is rebuilt from scratch, so it has no free blocks and at most one data
+/// block that has free entries.
+pub(super) fn store(
+    file: &dyn Inode,
+    type_: QuotaType,
+    format: QuotaFormat,
+    info: &QuotaInfo,
+    dquots: &BTreeMap<u32, DiskQuota>,
+) -> Result<()> {
+    let mut image = QuotaFileImage::new();
+    let entry_size = format.entry_size();
+    let entries_per_block = format.entries_per_block();
+
+    // The data block that is being filled, and the number of the entries in it.
+    let mut data_block: Option<(u32, usize)> = None;
+    for (&id, dquot) in dquots.iter() {
+        if dquot.is_empty() {
+            continue;
+        }
+
+        let (blk, nr_entries) = match data_block {
+            Some((blk, nr_entries)) if nr_entries < entries_per_block => (blk, nr_entries),
+            _ => (image.alloc_block(), 0),
+        };
+        let offset =
+            blk as usize * QT_BLKSIZE + size_of::<RawDataHeader>() + nr_entries * entry_size;
+        format.write_entry(id, dquot, &mut image.bytes[offset..offset + entry_size]);
+        image.set_data_entries(blk, nr_entries + 1);
+        data_block = Some((blk, nr_entries + 1));
+
+        image.insert_ref(id, blk);
+    }
+
+    let free_entry = match data_block {
+        Some((blk, nr_entries)) if nr_entries < entries_per_block => blk,
+        _ => 0,
+    };
+    let header = RawHeader {
+        magic: V2_MAGICS[type_ as usize],
+        version: format.version(),
+    };
+    let raw_info = RawInfo {
+        bgrace: info.bgrace.min(u32::MAX as u64) as u32,
+        igrace: info.igrace.min(u32::MAX as u64) as u32,
+        flags: info.flags,
+        blocks: image.nr_blocks(),
+        free_blk: 0,
+        free_entry,
+    };
+    image.bytes[..size_of::<RawHeader>()].copy_from_slice(header.as_bytes());
+    image.bytes[V2_DQINFOOFF..V2_DQINFOOFF + size_of::<RawInfo>()]
+        .copy_from_slice(raw_info.as_bytes());
+
+    file.write_bytes_at(0, &image.bytes)?;
+    if file.size() > image.bytes.len() {
+        file.resize(image.bytes.len())?;
+    }
+    Ok(())
+}
+
+/// The content of a quota file that is being built.
+struct QuotaFileImage {
+    bytes: Vec<u8>,
+}
+
+impl QuotaFileImage {
+    /// Creates an image with the first block and the root of the tree.
+    fn new() -> Self {
+        Self {
+            bytes: vec![0u8; (QT_TREEOFF as usize + 1) * QT_BLKSIZE],
+        }
+    }
+
+    fn nr_blocks(&self) -> u32 {
+        (self.bytes.len() / QT_BLKSIZE) as u32
+    }
+
+    fn alloc_block(&mut self) -> u32 {
+        let blk = self.nr_blocks();
+        self.bytes.resize(self.bytes.len() + QT_BLKSIZE, 0);
+        blk
+    }
+
+    fn set_data_entries(&mut self, blk: u32, nr_entries: usize) {
+        let offset = blk as usize * QT_BLKSIZE + offset_of!(RawDataHeader, entries);
+        self.bytes[offset..offset + size_of::<u16>()]
+            .copy_from_slice(&(nr_entries as u16).to_le_bytes());
+    }
+
+    /// Inserts the reference from the tree to the data block of the ID.
+    fn insert_ref(&mut self, id: u32, data_blk: u32) {
+        let mut blk = QT_TREEOFF;
+        for depth in 0..QT_TREEDEPTH {
+            let idx = ((id >> ((QT_TREEDEPTH - 1 - depth) * 8)) & 0xff) as usize;
+            debug_assert!(idx < QT_REFS_PER_BLOCK);
+            let offset = blk as usize * QT_BLKSIZE + idx * size_of::<u32>();
+
+            let child = if depth == QT_TREEDEPTH - 1 {
+                data_blk
+            } else {
+                match u32::from_le_bytes(self.bytes[offset..offset + 4].try_into().unwrap()) {
+                    0 => self.alloc_block(),
+                    child => child,
+                }
+            };
+            self.bytes[offset..offset + 4].copy_from_slice(&child.to_le_bytes());
+            blk = child;
+        }
+    }
+}
@@ -0,0 +1,589 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Disk quotas.
+//!
+//! Disk quotas limit the space and the number of inodes used by each user, group or
+//! project on a filesystem. A filesystem charges the usage of an inode to the IDs of
+//! the inode, and the charge fails with `EDQUOT` if a hard limit is exceeded, or if a
+//! soft limit has been exceeded for longer than the grace time.
+//!
+//! The usage and limits are stored in quota files, which are either regular files that
+//! are specified when turning on quotas, or hidden files that are managed by the
+//! filesystem itself.
+//!
+//! Reference: <https://man7.org/linux/man-pages/man2/quotactl.2.html>
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+pub use self::format::QuotaFormat;
+use super::inode::Inode;
+use crate::{
+    prelude::*,
+    process::{credentials::capabilities::CapSet, posix_thread::AsPosixThread},
+    thread::Thread,
+};
+
+mod format;
+
+/// The number of quota types.
+pub const MAXQUOTAS: usize = 3;
+
+/// The type of a quota.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+pub enum QuotaType {
+    User = 0,
+    Group = 1,
+    Project = 2,
+}
+
+impl QuotaType {
+    const ALL: [Self; MAXQUOTAS] = [Self::User, Self::Group, Self::Project];
+}
+
+/// The IDs that the usage of an inode is charged to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaIds {
+    pub uid: u32,
+    pub gid: u32,
+    pub projid: u32,
+}
+
+impl QuotaIds {
+    fn get(&self, type_: QuotaType) -> u32 {
+        match type_ {
+            QuotaType::User => self.uid,
+            QuotaType::Group => self.gid,
+            QuotaType::Project => self.projid,
+        }
+    }
+}
+
+/// The usage and limits of an ID.
+///
+/// The space is in bytes, and the times are the seconds since the Epoch, after which
+/// the soft limits are enforced as hard limits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiskQuota {
+    pub bhardlimit: u64,
+    pub bsoftlimit: u64,
+    pub curspace: u64,
+    pub ihardlimit: u64,
+    pub isoftlimit: u64,
+    pub curinodes: u64,
+    pub btime: u64,
+    pub itime: u64,
+}
+
+impl DiskQuota {
+    /// Returns whether the ID has no usage and no limits.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Checks whether `space` bytes and `inodes` inodes can be charged.
+    fn check(&self, space: u64, inodes: u64, now: u64, ignores_limits: bool) -> Result<()> {
+        if ignores_limits {
+            return Ok(());
+        }
+
+        let new_space = self.curspace.saturating_add(space);
+        if space > 0 && self.bhardlimit != 0 && new_space > self.bhardlimit {
+            return_errno_with_message!(Errno::EDQUOT, "the space hard limit is exceeded");
+        }
+        if space > 0
+            && self.bsoftlimit != 0
+            && new_space > self.bsoftlimit
+            && self.btime != 0
+            && now >= self.btime
+        {
+            return_errno_with_message!(Errno::EDQUOT, "the space grace time expires");
+        }
+
+        let new_inodes = self.curinodes.saturating_add(inodes);
+        if inodes > 0 && self.ihardlimit != 0 && new_inodes > self.ihardlimit {
+            return_errno_with_message!(Errno::EDQUOT, "the inode hard limit is exceeded");
+        }
+        if inodes > 0
+            && self.isoftlimit != 0
+            && new_inodes > self.isoftlimit
+            && self.itime != 0
+            && now >= self.itime
+        {
+            return_errno_with_message!(Errno::EDQUOT, "the inode grace time expires");
+        }
+
+        Ok(())
+    }
+
+    fn charge(&mut self, space: u64, inodes: u64, info: &QuotaInfo, now: u64) {
+        self.curspace = self.curspace.saturating_add(space);
+        self.curinodes = self.curinodes.saturating_add(inodes);
+        self.update_grace_times(info, now);
+    }
+
+    fn release(&mut self, space: u64, inodes: u64, info: &QuotaInfo, now: u64) {
+        self.curspace = self.curspace.saturating_sub(space);
+        self.curinodes = self.curinodes.saturating_sub(inodes);
+        self.update_grace_times(info, now);
+    }
+
+    /// Starts the grace times if the soft limits are exceeded,
+    /// or stops them if the usage drops below the soft limits.
+    fn update_grace_times(&mut self, info: &QuotaInfo, now: u64) {
+        if self.bsoftlimit != 0 && self.curspace > self.bsoftlimit {
+            if self.btime == 0 {
+                self.btime = now + info.bgrace;
+            }
+        } else {
+            self.btime = 0;
+        }
+
+        if self.isoftlimit != 0 && self.curinodes > self.isoftlimit {
+            if self.itime == 0 {
+                self.itime = now + info.igrace;
+            }
+        } else {
+            self.itime = 0;
+        }
+    }
+}
+
+/// The changes to the usage and limits of an ID.
+///
+/// The fields that are `None` are left unchanged.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiskQuotaUpdate {
+    /// The hard and soft limits of the space.
+    pub blimits: Option<(u64, u64)>,
+    pub curspace: Option<u64>,
+    /// The hard and soft limits of the inodes.
+    pub ilimits: Option<(u64, u64)>,
+    pub curinodes: Option<u64>,
+    pub btime: Option<u64>,
+    pub itime: Option<u64>,
+}
+
+/// The information of the quotas of a type.
+#[derive(Debug, Clone, Copy)]
+pub struct QuotaInfo {
+    /// The grace time of the space in seconds.
+    pub bgrace: u64,
+    /// The grace time of the inodes in seconds.
+    pub igrace: u64,
+    pub flags: u32,
+}
+
+/// The quotas of a type that are turned on.
+struct QuotaState {
+    format: QuotaFormat,
+    file: Arc<dyn Inode>,
+    info: QuotaInfo,
+    dquots: BTreeMap<u32, DiskQuota>,
+    /// Whether the limits are enforced. Otherwise, only the usage is tracked.
+    is_enforced: bool,
+    is_dirty: bool,
+}
+
+/// The disk quotas of a filesystem.
+pub struct DiskQuotas {
+    states: [Mutex<Option<QuotaState>>; MAXQUOTAS],
+    /// The inode numbers of the quota files, whose usage is not charged.
+    file_inos: [AtomicU64; MAXQUOTAS],
+    /// Whether the quotas are stored in hidden files, which are loaded on mounting.
+    has_hidden_files: bool,
+}
+
+impl DiskQuotas {
+    /// Creates disk quotas that are stored in the files specified on turning on quotas.
+    pub fn new() -> Self {
+        Self::new_inner(false)
+    }
+
+    /// Creates disk quotas that are stored in hidden files.
+    ///
+    /// The hidden files are loaded with [`Self::load_hidden_file`], after which the
+    /// usage is always tracked. Turning quotas on or off only enables or disables the
+    /// enforcement of the limits.
+    pub fn new_with_hidden_files() -> Self {
+        Self::new_inner(true)
+    }
+
+    fn new_inner(has_hidden_files: bool) -> Self {
+        Self {
+            states: [const { Mutex::new(None) }; MAXQUOTAS],
+            file_inos: [const { AtomicU64::new(0) }; MAXQUOTAS],
+            has_hidden_files,
+        }
+    }
+
+    /// Returns whether the quotas are stored in hidden files.
+    pub fn has_hidden_files(&self) -> bool {
+        self.has_hidden_files
+    }
+
+    /// Loads the quotas of a type from a hidden file.
+    pub fn load_hidden_file(
+        &self,
+        type_: QuotaType,
+        file: Arc<dyn Inode>,
+        format: QuotaFormat,
+    ) -> Result<()> {
+        debug_assert!(self.has_hidden_files);
+        self.load(type_, file, format, false)
+    }
+
+    /// Turns on the quotas of a type.
+    ///
+    /// If the quotas are stored in hidden files, `file` is ignored and the limits of
+    /// the loaded quotas are enforced.
+    pub fn turn_on(
+        &self,
+        type_: QuotaType,
+        format: QuotaFormat,
+        file: Option<Arc<dyn Inode>>,
+    ) -> Result<()> {
+        if self.has_hidden_files {
+            let mut state = self.states[type_ as usize].lock();
+            let Some(state) = state.as_mut() else {
+                return_errno_with_message!(Errno::EPERM, "the quota file does not exist");
+            };
+            if state.is_enforced {
+                return_errno_with_message!(Errno::EBUSY, "the quotas are already turned on");
+            }
+            state.is_enforced = true;
+            return Ok(());
+        }
+
+        let Some(file) = file else {
+            return_errno_with_message!(Errno::EINVAL, "the quota file is not specified");
+        };
+        if !file.type_().is_regular_file() {
+            return_errno_with_message!(Errno::EACCES, "the quota file is not a regular file");
+        }
+        self.load(type_, file, format, true)
+    }
+
+    fn load(
+        &self,
+        type_: QuotaType,
+        file: Arc<dyn Inode>,
+        format: QuotaFormat,
+        is_enforced: bool,
+    ) -> Result<()> {
+        let mut state = self.states[type_ as usize].lock();
+        if state.is_some() {
+            return_errno_with_message!(Errno::EBUSY, "the quotas are already turned on");
+        }
+
+        let (info, dquots) = format::load(file.as_ref(), type_, format)?;
+        // The writes to the quota file must not be charged, since they happen with the
+        // quotas locked.
+        self.file_inos[type_ as usize].store(file.ino(), Ordering::Relaxed);
+        *state = Some(QuotaState {
+            format,
+            file,
+            info,
+            dquots,
+            is_enforced,
+            is_dirty: false,
+        });
+        Ok(())
+    }
+
+    /// Turns off the quotas of a type.
+    ///
+    /// If the quotas are stored in hidden files, the usage is still tracked.
+    pub fn turn_off(&self, type_: QuotaType) -> Result<()> {
+        let mut state = self.states[type_ as usize].lock();
+        if self.has_hidden_files {
+            if let Some(state) = state.as_mut() {
+                state.is_enforced = false;
+            }
+            return Ok(());
+        }
+
+        let Some(state) = state.take() else {
+            return Ok(());
+        };
+        self.file_inos[type_ as usize].store(0, Ordering::Relaxed);
+        if state.is_dirty {
+            format::store(
+                state.file.as_ref(),
+                type_,
+                state.format,
+                &state.info,
+                &state.dquots,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Returns whether the inode is a quota file, whose usage is not charged.
+    pub fn is_quota_file(&self, ino: u64) -> bool {
+        self.file_inos
+            .iter()
+            .any(|file_ino| file_ino.load(Ordering::Relaxed) == ino)
+    }
+
+    /// Returns the format of the quota file of a type.
+    pub fn format(&self, type_: QuotaType) -> Result<QuotaFormat> {
+        self.with_state(type_, |state| Ok(state.format))
+    }
+
+    /// Returns the information of the quotas of a type.
+    pub fn info(&self, type_: QuotaType) -> Result<QuotaInfo> {
+        self.with_state(type_, |state| Ok(state.info))
+    }
+
+    /// Sets the grace times and the flags of the quotas of a type.
+    pub fn set_info(
+        &self,
+        type_: QuotaType,
+        bgrace: Option<u64>,
+        igrace: Option<u64>,
+        flags: Option<u32>,
+    ) -> Result<()> {
+        self.with_state(type_, |state| {
+            if let Some(bgrace) = bgrace {
+                state.info.bgrace = bgrace;
+            }
+            if let Some(igrace) = igrace {
+                state.info.igrace = igrace;
+            }
+            if let Some(flags) = flags {
+                state.info.flags = flags;
+            }
+            state.is_dirty = true;
+            Ok(())
+        })
+    }
+
+    /// Returns the usage and limits of an ID.
+    pub fn get_quota(&self, type_: QuotaType, id: u32) -> Result<DiskQuota> {
+        self.with_state(type_, |state| {
+            Ok(state.dquots.get(&id).copied().unwrap_or_default())
+        })
+    }
+
+    /// Returns the first ID that is not less than `id` and has usage or limits,
+    /// together with its usage and limits.
+    pub fn next_quota(&self, type_: QuotaType, id: u32) -> Result<(u32, DiskQuota)> {
+        self.with_state(type_, |state| {
+            state
+                .dquots
+                .range(id..)
+                .find(|(_, dquot)| !dquot.is_empty())
+                .map(|(id, dquot)| (*id, *dquot))
+                .ok_or_else(|| Error::with_message(Errno::ENOENT, "no more quotas"))
+        })
+    }
+
+    /// Sets the usage and limits of an ID.
+    pub fn set_quota(&self, type_: QuotaType, id: u32, update: &DiskQuotaUpdate) -> Result<()> {
+        let now = now_secs();
+        self.with_state(type_, |state| {
+            let max_space = state.format.max_space_limit();
+            let max_inodes = state.format.max_inode_limit();
+            if update
+                .blimits
+                .is_some_and(|(hard, soft)| hard > max_space || soft > max_space)
+                || update
+                    .ilimits
+                    .is_some_and(|(hard, soft)| hard > max_inodes || soft > max_inodes)
+            {
+                return_errno_with_message!(Errno::ERANGE, "the limits are too large");
+            }
+
+            let dquot = state.dquots.entry(id).or_default();
+            if let Some((hard, soft)) = update.blimits {
+                dquot.bhardlimit = hard;
+                dquot.bsoftlimit = soft;
+            }
+            if let Some(curspace) = update.curspace {
+                dquot.curspace = curspace;
+            }
+            if let Some((hard, soft)) = update.ilimits {
+                dquot.ihardlimit = hard;
+                dquot.isoftlimit = soft;
+            }
+            if let Some(curinodes) = update.curinodes {
+                dquot.curinodes = curinodes;
+            }
+            dquot.update_grace_times(&state.info, now);
+            if let Some(btime) = update.btime {
+                dquot.btime = btime;
+            }
+            if let Some(itime) = update.itime {
+                dquot.itime = itime;
+            }
+            state.is_dirty = true;
+            Ok(())
+        })
+    }
+
+    /// Charges the space and inodes to the IDs.
+    ///
+    /// Nothing is charged if a limit is exceeded for any type of the quotas.
+    pub fn charge(&self, ids: &QuotaIds, space: u64, inodes: u64) -> Result<()> {
+        let now = now_secs();
+        let ignores_limits = can_ignore_limits();
+        let mut states = self.lock_states();
+
+        for (type_, state) in QuotaType::ALL.into_iter().zip(states.iter_mut()) {
+            let Some(state) = state.as_mut() else {
+                continue;
+            };
+            let dquot = state
+                .dquots
+                .get(&ids.get(type_))
+                .copied()
+                .unwrap_or_default();
+            dquot.check(space, inodes, now, ignores_limits || !state.is_enforced)?;
+        }
+
+        for (type_, state) in QuotaType::ALL.into_iter().zip(states.iter_mut()) {
+            let Some(state) = state.as_mut() else {
+                continue;
+            };
+            let info = state.info;
+            let dquot = state.dquots.entry(ids.get(type_)).or_default();
+            dquot.charge(space, inodes, &info, now);
+            state.is_dirty = true;
+        }
+
+        Ok(())
+    }
+
+    /// Releases the space and inodes charged to the IDs.
+    pub fn release(&self, ids: &QuotaIds, space: u64, inodes: u64) {
+        let now = now_secs();
+        let mut states = self.lock_states();
+
+        for (type_, state) in QuotaType::ALL.into_iter().zip(states.iter_mut()) {
+            let Some(state) = state.as_mut() else {
+                continue;
+            };
+            let info = state.info;
+            let dquot = state.dquots.entry(ids.get(type_)).or_default();
+            dquot.release(space, inodes, &info, now);
+            state.is_dirty = true;
+        }
+    }
+
+    /// Transfers the usage of an inode between IDs, e.g., when its owner is changed.
+    ///
+    /// Nothing is transferred if a limit of the new IDs is exceeded.
+    pub fn transfer(&self, from: &QuotaIds, to: &QuotaIds, space: u64, inodes: u64) -> Result<()> {
+        let now = now_secs();
+        let ignores_limits = can_ignore_limits();
+        let mut states = self.lock_states();
+
+        for (type_, state) in QuotaType::ALL.into_iter().zip(states.iter_mut()) {
+            let Some(state) = state.as_mut() else {
+                continue;
+            };
+            if from.get(type_) == to.get(type_) {
+                continue;
+            }
+            let dquot = state
+                .dquots
+                .get(&to.get(type_))
+                .copied()
+                .unwrap_or_default();
+            dquot.check(space, inodes, now, ignores_limits || !state.is_enforced)?;
+        }
+
+        for (type_, state) in QuotaType::ALL.into_iter().zip(states.iter_mut()) {
+            let Some(state) = state.as_mut() else {
+                continue;
+            };
+            if from.get(type_) == to.get(type_) {
+                continue;
+            }
+            let info = state.info;
+            let from_dquot = state.dquots.entry(from.get(type_)).or_default();
+            from_dquot.release(space, inodes, &info, now);
+            let to_dquot = state.dquots.entry(to.get(type_)).or_default();
+            to_dquot.charge(space, inodes, &info, now);
+            state.is_dirty = true;
+        }
+
+        Ok(())
+    }
+
+    /// Writes back the quotas to the quota files.
+    pub fn sync(&self) -> Result<()> {
+        for (type_, state) in QuotaType::ALL.into_iter().zip(self.states.iter()) {
+            let mut state = state.lock();
+            let Some(state) = state.as_mut() else {
+                continue;
+            };
+            if !state.is_dirty {
+                continue;
+            }
+            format::store(
+                state.file.as_ref(),
+                type_,
+                state.format,
+                &state.info,
+                &state.dquots,
+            )?;
+            state.is_dirty = false;
+        }
+        Ok(())
+    }
+
+    fn with_state<R>(
+        &self,
+        type_: QuotaType,
+        f: impl FnOnce(&mut QuotaState) -> Result<R>,
+    ) -> Result<R> {
+        let mut state = self.states[type_ as usize].lock();
+        match state.as_mut() {
+            Some(state) => f(state),
+            None => return_errno_with_message!(Errno::ESRCH, "the quotas are not turned on"),
+        }
+    }
+
+    /// Locks the states of all types in order.
+    fn lock_states(&self) -> [MutexGuard<'_, Option<QuotaState>>; MAXQUOTAS] {
+        self.states.each_ref().map(|state| state.lock())
+    }
+}
+
+impl Default for DiskQuotas {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for DiskQuotas {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("DiskQuotas")
+            .field("has_hidden_files", &self.has_hidden_files)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Returns whether the current thread can exceed the limits,
+/// i.e., whether it has the `CAP_SYS_RESOURCE` capability.
+fn can_ignore_limits() -> bool {
+    // Kernel threads are not limited.
+    let Some(current) = Thread::current() else {
+        return true;
+    };
+    let Some(posix_thread) = current.as_posix_thread() else {
+        return true;
+    };
+    posix_thread
+        .credentials()
+        .effective_capset()
+        .contains(CapSet::SYS_RESOURCE)
+}
+
+fn now_secs() -> u64 {
+    crate::time::clocks::RealTimeCoarseClock::get()
+        .read_time()
+        .as_secs()
+}
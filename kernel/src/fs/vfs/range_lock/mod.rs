@@ -10,11 +10,42 @@ use crate::{prelude::*, process::Pid};
 
 mod range;
 
+/// The processes that are blocked by the record locks of other processes, which are
+/// tracked to detect deadlocks.
+///
+/// Each entry maps the waker of a blocked thread to the process of the thread and the
+/// process owning the lock that blocks it.
+static BLOCKED_PROCESSES: Mutex<BTreeMap<usize, (Pid, Pid)>> = Mutex::new(BTreeMap::new());
+
+/// The owner of a POSIX advisory file range lock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeLockOwner {
+    /// A process, which owns the traditional record locks set by `F_SETLK`.
+    ///
+    /// The locks are released when the process closes any file descriptor of the file
+    /// or exits.
+    Process(Pid),
+    /// An open file description, which owns the locks set by `F_OFD_SETLK`.
+    ///
+    /// The open file description is identified by the address of its `InodeHandle`.
+    /// The locks are released when the open file description is dropped.
+    OpenFile(usize),
+}
+
+/// Whether setting a POSIX advisory file range lock waits for the conflicting locks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeLockWaitMode {
+    /// Blocks until the conflicting locks are released, as `F_SETLKW` does.
+    Wait,
+    /// Fails with `EAGAIN` if there are conflicting locks, as `F_SETLK` does.
+    NoWait,
+}
+
 /// The metadata of a POSIX advisory file range lock.
 #[derive(Debug, Clone)]
 struct RangeLock {
-    /// Owner of the lock, representing the process holding the lock
-    owner: Pid,
+    /// Owner of the lock, representing the process or the open file holding the lock
+    owner: RangeLockOwner,
     /// Type of lock: can be F_RDLCK (read lock), F_WRLCK (write lock), or F_UNLCK (unlock)
     type_: RangeLockType,
     /// Range of the lock which specifies the portion of the file being locked
@@ -35,8 +66,13 @@ impl RangeLockItem {
     /// Creates a new instance with the given lock type and the file range.
     /// The new instance will be associated with the current process.
     pub fn new(type_: RangeLockType, range: FileRange) -> Self {
+        Self::new_with_owner(type_, range, RangeLockOwner::Process(current!().pid()))
+    }
+
+    /// Creates a new instance with the given lock type, the file range and the owner.
+    pub fn new_with_owner(type_: RangeLockType, range: FileRange, owner: RangeLockOwner) -> Self {
         let lock = RangeLock {
-            owner: current!().pid(),
+            owner,
             type_,
             range,
         };
@@ -56,13 +92,13 @@ impl RangeLockItem {
         self.lock.type_ = type_;
     }
 
-    /// Returns the owner (process ID) of the lock
-    pub fn owner(&self) -> Pid {
+    /// Returns the owner of the lock
+    pub fn owner(&self) -> RangeLockOwner {
         self.lock.owner
     }
 
-    /// Sets the owner of the lock to the specified process ID
-    pub fn set_owner(&mut self, owner: Pid) {
+    /// Sets the owner of the lock to the specified owner
+    pub fn set_owner(&mut self, owner: RangeLockOwner) {
         self.lock.owner = owner;
     }
 
@@ -79,7 +115,7 @@ impl RangeLockItem {
     /// Checks if this lock conflicts with another lock
     /// Returns true if there is a conflict, otherwise false
     pub fn conflict_with(&self, other: &Self) -> bool {
-        // If locks are owned by the same process, they do not conflict
+        // If locks are owned by the same owner, they do not conflict
         if self.owner() == other.owner() {
             return false;
         }
@@ -176,7 +212,7 @@ impl Clone for RangeLockItem {
 /// List of File POSIX advisory range locks.
 ///
 /// Rule of ordering:
-/// Locks are sorted by owner process, then by the starting offset.
+/// Locks are sorted by owner, then by the starting offset.
 ///
 /// Rule of merging:
 /// Adjacent and overlapping locks with same owner and type will be merged.
@@ -220,11 +256,13 @@ impl RangeLockList {
     /// If no conflicting locks exist, the lock is set and the function returns `Ok(())`.
     /// If a conflicting lock exists:
     /// - If waker is not `None`, it is added to the conflicting lock's waitqueue, and the function returns `EAGAIN`.
+    ///   If waiting for the conflicting lock would cause a deadlock, the function returns `EDEADLK` instead.
     /// - If waker is `None`, the function returns `EAGAIN`.
     fn try_set_lock(&self, req_lock: &RangeLockItem, waker: Option<&Arc<Waker>>) -> Result<()> {
         let mut list = self.inner.write();
         if let Some(conflict_lock) = list.iter().find(|l| req_lock.conflict_with(l)) {
             if let Some(waker) = waker {
+                Self::block_on(req_lock, conflict_lock, waker)?;
                 conflict_lock.waitqueue.enqueue(waker.clone());
             }
             return_errno_with_message!(Errno::EAGAIN, "the file is locked");
@@ -236,28 +274,57 @@ impl RangeLockList {
 
     /// Sets a lock on the file.
     ///
-    /// If the lock is non-blocking and there is a conflict, return `Err(Errno::EAGAIN)`.
+    /// If the wait mode is `NoWait` and there is a conflict, return `Err(Errno::EAGAIN)`.
     /// Otherwise, block the current process until the lock can be set or it is interrupted by a signal.
-    pub fn set_lock(&self, req_lock: &RangeLockItem, is_nonblocking: bool) -> Result<()> {
+    pub fn set_lock(&self, req_lock: &RangeLockItem, wait_mode: RangeLockWaitMode) -> Result<()> {
         debug!(
-            "set_lock with RangeLock: {:?}, is_nonblocking: {}",
-            req_lock, is_nonblocking
+            "set_lock with RangeLock: {:?}, wait_mode: {:?}",
+            req_lock, wait_mode
         );
-        if is_nonblocking {
+        if wait_mode == RangeLockWaitMode::NoWait {
             self.try_set_lock(req_lock, None)
         } else {
             let (waiter, waker) = Waiter::new_pair();
-            waiter.pause_until(|| {
+            let result = waiter.pause_until(|| {
                 let result = self.try_set_lock(req_lock, Some(&waker));
                 if result.is_err_and(|err| err.error() == Errno::EAGAIN) {
                     None
                 } else {
                     Some(result)
                 }
-            })?
+            });
+            BLOCKED_PROCESSES
+                .lock()
+                .remove(&(Arc::as_ptr(&waker) as usize));
+            result?
         }
     }
 
+    /// Records that a process is blocked by the lock of another process.
+    ///
+    /// Like Linux, the deadlocks are only detected among the locks owned by processes.
+    /// If the owner of `conflict_lock` is (indirectly) waiting for the owner of
+    /// `req_lock`, this method fails with `EDEADLK`.
+    fn block_on(
+        req_lock: &RangeLockItem,
+        conflict_lock: &RangeLockItem,
+        waker: &Arc<Waker>,
+    ) -> Result<()> {
+        let (RangeLockOwner::Process(owner), RangeLockOwner::Process(blocker)) =
+            (req_lock.owner(), conflict_lock.owner())
+        else {
+            return Ok(());
+        };
+
+        let mut blocked_processes = BLOCKED_PROCESSES.lock();
+        if is_waiting_for(&blocked_processes, blocker, owner) {
+            return_errno_with_message!(Errno::EDEADLK, "the lock would cause a deadlock");
+        }
+        blocked_processes.insert(Arc::as_ptr(waker) as usize, (owner, blocker));
+
+        Ok(())
+    }
+
     /// Insert a lock into the list.
     fn insert_lock_into_list(
         list: &mut RwMutexWriteGuard<Vec<RangeLockItem>>,
@@ -402,6 +469,36 @@ impl RangeLockList {
     }
 }
 
+/// Returns whether the `waiting` process is (indirectly) waiting for the `target` process.
+///
+/// A process may have multiple blocked threads, so all the processes that block any of
+/// them are followed.
+fn is_waiting_for(
+    blocked_processes: &BTreeMap<usize, (Pid, Pid)>,
+    waiting: Pid,
+    target: Pid,
+) -> bool {
+    let mut visited = BTreeSet::new();
+    let mut pending = vec![waiting];
+
+    while let Some(blocked) = pending.pop() {
+        if blocked == target {
+            return true;
+        }
+        if !visited.insert(blocked) {
+            continue;
+        }
+        pending.extend(
+            blocked_processes
+                .values()
+                .filter(|(waiter, _)| *waiter == blocked)
+                .map(|(_, blocker)| *blocker),
+        );
+    }
+
+    false
+}
+
 impl Default for RangeLockList {
     fn default() -> Self {
         Self::new()
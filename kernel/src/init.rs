@@ -40,18 +40,23 @@ fn init() {
     crate::util::random::init();
     crate::driver::init();
     crate::time::init();
+    crate::trace::init();
     crate::net::init();
     crate::sched::init();
+    crate::cpu::freq::init();
     crate::process::init();
     crate::fs::init();
     crate::security::init();
+    crate::vm::init();
 }
 
 fn init_on_each_cpu() {
     crate::sched::init_on_each_cpu();
+    crate::cpu::freq::init_on_each_cpu();
     crate::process::init_on_each_cpu();
     crate::fs::init_on_each_cpu();
     crate::time::init_on_each_cpu();
+    crate::perf::init_on_each_cpu();
 }
 
 fn ap_init() {
@@ -81,6 +86,10 @@ fn ap_init() {
 // In addition,
 // doing more work in the idle task may have negative impact on
 // the latency to switching from the idle task to a useful, runnable one.
+//
+// The only exception is the scheduler,
+// which is initialized before any idle task is spawned
+// and steals tasks from other CPUs without blocking (see `idle`).
 
 fn bsp_idle_loop() {
     log::info!("[kernel] Idle thread for CPU #0 started");
@@ -97,12 +106,12 @@ fn bsp_idle_loop() {
             break init_process;
         };
 
-        ostd::task::halt_cpu();
+        idle();
     };
 
     // Wait till the init process becomes zombie.
     while !init_process.status().is_zombie() {
-        ostd::task::halt_cpu();
+        idle();
     }
 
     // According to the Linux implementation, we should panic once the init process exits.
@@ -116,6 +125,15 @@ fn bsp_idle_loop() {
     ostd::power::poweroff(exit_code);
 }
 
+/// Runs the tasks stolen from other CPUs if there are any, or halts the CPU otherwise.
+fn idle() {
+    if crate::sched::idle_balance() {
+        ostd::task::Task::yield_now();
+    } else {
+        ostd::task::halt_cpu();
+    }
+}
+
 fn ap_idle_loop() {
     log::info!(
         "[kernel] Idle thread for CPU #{} started",
@@ -124,7 +142,7 @@ fn ap_idle_loop() {
     );
 
     loop {
-        ostd::task::halt_cpu();
+        idle();
     }
 }
 
@@ -158,9 +176,12 @@ fn init_in_first_kthread(path_resolver: &PathResolver) {
     // in case any irq handler uses work queue as bottom half
     crate::thread::work_queue::init_in_first_kthread();
     crate::device::init_in_first_kthread();
+    crate::sched::init_in_first_kthread();
+    crate::trace::init_in_first_kthread();
     crate::net::init_in_first_kthread();
     crate::fs::init_in_first_kthread(path_resolver);
     crate::ipc::init_in_first_kthread();
+    crate::power::init_in_first_kthread();
     #[cfg(any(target_arch = "x86_64", target_arch = "riscv64"))]
     crate::vdso::init_in_first_kthread();
 }
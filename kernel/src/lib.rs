@@ -34,6 +34,7 @@ extern crate ostd_pod;
 #[cfg_attr(target_arch = "loongarch64", path = "arch/loongarch/mod.rs")]
 mod arch;
 
+mod bpf;
 mod context;
 mod cpu;
 mod device;
@@ -44,6 +45,8 @@ mod fs;
 mod init;
 mod ipc;
 mod net;
+mod perf;
+mod power;
 mod prelude;
 mod process;
 mod sched;
@@ -51,6 +54,7 @@ mod security;
 mod syscall;
 mod thread;
 mod time;
+mod trace;
 mod util;
 // TODO: Add vDSO support for other architectures.
 #[cfg(any(target_arch = "x86_64", target_arch = "riscv64"))]
@@ -31,7 +31,11 @@ pub(super) fn init() {
 }
 
 /// Determines if a given IP endpoint's address is a known broadcast address.
+///
+/// IPv6 has no broadcast addresses, so this function always returns `false` for IPv6 endpoints.
 pub fn is_broadcast_endpoint(endpoint: &IpEndpoint) -> bool {
-    let IpAddress::Ipv4(ipv4_addr) = &endpoint.addr;
-    BROADCAST_ADDRS.get().unwrap().contains(ipv4_addr)
+    match &endpoint.addr {
+        IpAddress::Ipv4(ipv4_addr) => BROADCAST_ADDRS.get().unwrap().contains(ipv4_addr),
+        IpAddress::Ipv6(_) => false,
+    }
 }
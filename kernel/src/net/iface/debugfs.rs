@@ -0,0 +1,53 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Exposes the internal states of the network interfaces in debugfs
+//! (i.e., `/sys/kernel/debug/net`).
+
+use super::{Iface, iter_all_ifaces};
+use crate::{
+    fs::debugfs::{self, DebugDir, DebugFile},
+    prelude::*,
+};
+
+pub(super) fn init_in_first_kthread() {
+    let net_dir = DebugDir::new("net", Vec::new());
+    for iface in iter_all_ifaces() {
+        if let Err(err) = net_dir.add_child(new_iface_dir(&iface)) {
+            warn!(
+                "failed to add the network interface '{}' to debugfs: {:?}",
+                iface.name(),
+                err
+            );
+        }
+    }
+
+    if let Err(err) = debugfs::register_dir(net_dir) {
+        warn!("failed to register the network stack in debugfs: {:?}", err);
+    }
+}
+
+fn new_iface_dir(iface: &Arc<Iface>) -> Arc<DebugDir> {
+    let files = vec![
+        DebugFile::new("index", {
+            let iface = iface.clone();
+            move || format!("{}\n", iface.index())
+        }),
+        DebugFile::new("flags", {
+            let iface = iface.clone();
+            move || format!("{:?}\n", iface.flags())
+        }),
+        DebugFile::new("mtu", {
+            let iface = iface.clone();
+            move || format!("{}\n", iface.mtu())
+        }),
+        DebugFile::new("ipv4_addr", {
+            let iface = iface.clone();
+            move || match iface.ipv4_addr() {
+                Some(addr) => format!("{}\n", addr),
+                None => String::from("none\n"),
+            }
+        }),
+    ];
+
+    DebugDir::new(iface.name().to_string(), files)
+}
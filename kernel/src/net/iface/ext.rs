@@ -1,7 +1,18 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use core::time::Duration;
+
 use super::sched::PollScheduler;
-use crate::net::socket::ip::{DatagramObserver, StreamObserver};
+use crate::{
+    net::{
+        netfilter::IptablesFilter,
+        socket::{
+            ip::{DatagramObserver, StreamObserver},
+            packet::PacketObserver,
+        },
+    },
+    time::{Clock, clocks::RealTimeClock},
+};
 
 pub struct BigtcpExt;
 
@@ -10,4 +21,16 @@ impl aster_bigtcp::ext::Ext for BigtcpExt {
 
     type TcpEventObserver = StreamObserver;
     type UdpEventObserver = DatagramObserver;
+    type RawEventObserver = DatagramObserver;
+    type PacketEventObserver = PacketObserver;
+
+    type PacketFilter = IptablesFilter;
+
+    type WallClock = RealTimeClock;
+}
+
+impl aster_bigtcp::time::WallClock for RealTimeClock {
+    fn now() -> Duration {
+        RealTimeClock::get().read_time()
+    }
 }
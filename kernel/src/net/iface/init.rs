@@ -1,40 +1,139 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use alloc::borrow::ToOwned;
-use core::slice::Iter;
+use alloc::{borrow::ToOwned, vec};
 
 use aster_bigtcp::{
     device::WithDevice,
     iface::{InterfaceFlags, InterfaceType},
+    wire::EthernetAddress,
 };
 use aster_softirq::BottomHalfDisabled;
+use ostd::sync::{RcuOption, rcu_read_lock};
 use spin::Once;
 
-use super::{Iface, poll::poll_ifaces};
+use super::{
+    Iface,
+    poll::{poll_ifaces, spawn_background_poll_thread},
+    sysfs,
+};
 use crate::{
     net::iface::{broadcast, sched::PollScheduler},
     prelude::*,
+    util::random::getrandom,
 };
 
-static IFACES: Once<Vec<Arc<Iface>>> = Once::new();
+/// The ifaces created at boot time, which are never removed.
+static BOOT_IFACES: Once<Vec<Arc<Iface>>> = Once::new();
+
+/// All the ifaces, including the ones added at runtime (e.g., TUN/TAP ifaces).
+///
+/// The lock is only taken to add or remove ifaces. The readers look up the ifaces in
+/// [`IFACES_VIEW`] instead.
+static IFACES: SpinLock<Vec<Arc<Iface>>, BottomHalfDisabled> = SpinLock::new(Vec::new());
+
+/// A read-only copy of [`IFACES`], which is published via RCU whenever [`IFACES`] is changed.
+///
+/// The ifaces are looked up to route every packet, so they are read without locking. The copy
+/// holds weak references, so that the last reference to a removed iface is never dropped in the
+/// RCU callbacks.
+static IFACES_VIEW: RcuOption<Box<Vec<Weak<Iface>>>> = RcuOption::new_none();
 
 pub fn loopback_iface() -> &'static Arc<Iface> {
-    &IFACES.get().unwrap()[0]
+    &BOOT_IFACES.get().unwrap()[0]
 }
 
 pub fn virtio_iface() -> Option<&'static Arc<Iface>> {
-    IFACES.get().unwrap().get(1)
+    BOOT_IFACES.get().unwrap().get(1)
+}
+
+/// Returns an iterator over all the ifaces.
+///
+/// The iterator yields the ifaces that exist when this function is called.
+pub fn iter_all_ifaces() -> vec::IntoIter<Arc<Iface>> {
+    let guard = rcu_read_lock();
+    let Some(ifaces) = IFACES_VIEW.read_with(&guard) else {
+        return Vec::new().into_iter();
+    };
+
+    let ifaces: Vec<_> = ifaces.iter().filter_map(Weak::upgrade).collect();
+    ifaces.into_iter()
+}
+
+/// Publishes the changed ifaces to the readers of [`IFACES_VIEW`].
+fn publish_ifaces(ifaces: &[Arc<Iface>]) {
+    let view = ifaces.iter().map(Arc::downgrade).collect();
+    IFACES_VIEW.update(Some(Box::new(view)));
+}
+
+/// Adds an iface at runtime.
+///
+/// The iface is polled by its own background thread until it is removed by [`remove_iface`].
+///
+/// This function fails with [`Errno::EEXIST`] if another iface has the same name.
+pub fn add_iface(iface: Arc<Iface>) -> Result<()> {
+    {
+        let mut ifaces = IFACES.lock();
+        if ifaces.iter().any(|other| other.name() == iface.name()) {
+            return_errno_with_message!(Errno::EEXIST, "the iface name is already in use");
+        }
+        ifaces.push(iface.clone());
+        publish_ifaces(&ifaces);
+    }
+
+    sysfs::add_iface(&iface);
+    spawn_background_poll_thread(iface);
+
+    Ok(())
 }
 
-pub fn iter_all_ifaces() -> Iter<'static, Arc<Iface>> {
-    IFACES.get().unwrap().iter()
+/// Removes an iface added by [`add_iface`].
+pub fn remove_iface(iface: &Arc<Iface>) {
+    {
+        let mut ifaces = IFACES.lock();
+        ifaces.retain(|other| other.index() != iface.index());
+        publish_ifaces(&ifaces);
+    }
+    sysfs::remove_iface(iface);
+    iface.sched_poll().stop();
+}
+
+/// Returns the name with `%d` in the template replaced by the first unused number.
+///
+/// Note that the name may be taken by others before the iface is added by [`add_iface`].
+pub fn alloc_iface_name(template: &str) -> Result<String> {
+    /// The maximum number of ifaces created from the same name template.
+    const MAX_IFACES_PER_TEMPLATE: usize = 32768;
+    const IFNAMSIZ: usize = 16;
+
+    let ifaces = iter_all_ifaces().collect::<Vec<_>>();
+
+    for index in 0..MAX_IFACES_PER_TEMPLATE {
+        let name = template.replacen("%d", &index.to_string(), 1);
+        if name.len() >= IFNAMSIZ {
+            break;
+        }
+        if ifaces.iter().all(|iface| iface.name() != name) {
+            return Ok(name);
+        }
+    }
+
+    return_errno_with_message!(Errno::ENFILE, "no iface names are available")
+}
+
+/// Generates a random locally administered unicast Ethernet address for a virtual iface.
+pub(in crate::net) fn random_ether_addr() -> EthernetAddress {
+    let mut addr = [0u8; 6];
+    getrandom(&mut addr);
+    addr[0] &= !0x01;
+    addr[0] |= 0x02;
+    EthernetAddress(addr)
 }
 
 // TODO: Support multiple network devices and avoid the hardcoded device name.
 const VIRTIO_DEVICE_NAME: &str = aster_virtio::device::network::DEVICE_NAME;
 
 pub fn init() {
-    IFACES.call_once(|| {
+    BOOT_IFACES.call_once(|| {
         let mut ifaces = Vec::with_capacity(2);
 
         // Initialize loopback before virtio
@@ -47,6 +146,11 @@ pub fn init() {
 
         ifaces
     });
+    {
+        let mut ifaces = IFACES.lock();
+        *ifaces = BOOT_IFACES.get().unwrap().clone();
+        publish_ifaces(&ifaces);
+    }
 
     if let Some(iface_virtio) = virtio_iface() {
         let callback = || iface_virtio.poll();
@@ -63,11 +167,13 @@ fn new_loopback() -> Arc<Iface> {
     use aster_bigtcp::{
         device::{Loopback, Medium},
         iface::IpIface,
-        wire::{Ipv4Address, Ipv4Cidr},
+        wire::{Ipv4Address, Ipv4Cidr, Ipv6Address, Ipv6Cidr},
     };
 
     const LOOPBACK_ADDRESS: Ipv4Address = Ipv4Address::new(127, 0, 0, 1);
     const LOOPBACK_ADDRESS_PREFIX_LEN: u8 = 8; // mask: 255.0.0.0
+    const LOOPBACK_IPV6_ADDRESS: Ipv6Address = Ipv6Address::LOCALHOST;
+    const LOOPBACK_IPV6_ADDRESS_PREFIX_LEN: u8 = 128;
 
     struct Wrapper(Mutex<Loopback>);
 
@@ -90,26 +196,36 @@ fn new_loopback() -> Arc<Iface> {
         | InterfaceFlags::RUNNING
         | InterfaceFlags::LOWER_UP;
 
-    IpIface::new(
+    let iface = IpIface::new(
         Wrapper(Mutex::new(Loopback::new(Medium::Ip))),
-        Ipv4Cidr::new(LOOPBACK_ADDRESS, LOOPBACK_ADDRESS_PREFIX_LEN),
+        Some(Ipv4Cidr::new(LOOPBACK_ADDRESS, LOOPBACK_ADDRESS_PREFIX_LEN)),
         "lo".to_owned(),
         PollScheduler::new(),
         InterfaceType::LOOPBACK,
         flags,
-    ) as Arc<Iface>
+    ) as Arc<Iface>;
+
+    let is_added = iface.add_ipv6_addr(Ipv6Cidr::new(
+        LOOPBACK_IPV6_ADDRESS,
+        LOOPBACK_IPV6_ADDRESS_PREFIX_LEN,
+    ));
+    debug_assert!(is_added);
+
+    iface
 }
 
 fn new_virtio() -> Option<Arc<Iface>> {
     use aster_bigtcp::{
         iface::EtherIface,
-        wire::{EthernetAddress, Ipv4Address, Ipv4Cidr},
+        wire::{Ipv4Address, Ipv4Cidr},
     };
     use aster_network::AnyNetworkDevice;
 
     const VIRTIO_ADDRESS: Ipv4Address = Ipv4Address::new(10, 0, 2, 15);
     const VIRTIO_ADDRESS_PREFIX_LEN: u8 = 24; // mask: 255.255.255.0
     const VIRTIO_GATEWAY: Ipv4Address = Ipv4Address::new(10, 0, 2, 2);
+    // The IPv6 link-local address is generated from the MAC address, and the global IPv6 address
+    // is configured by SLAAC once the router advertises a prefix.
 
     let virtio_net = aster_network::get_device(VIRTIO_DEVICE_NAME)?;
 
@@ -140,8 +256,8 @@ fn new_virtio() -> Option<Arc<Iface>> {
     Some(EtherIface::new(
         Wrapper(virtio_net),
         EthernetAddress(ether_addr),
-        Ipv4Cidr::new(VIRTIO_ADDRESS, VIRTIO_ADDRESS_PREFIX_LEN),
-        VIRTIO_GATEWAY,
+        Some(Ipv4Cidr::new(VIRTIO_ADDRESS, VIRTIO_ADDRESS_PREFIX_LEN)),
+        Some(VIRTIO_GATEWAY),
         "eth0".to_owned(),
         PollScheduler::new(),
         flags,
@@ -0,0 +1,414 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The ioctl commands that query and configure the ifaces (e.g., `SIOCGIFCONF`).
+//!
+//! Like Linux, these commands are supported by all sockets, regardless of their families.
+//!
+//! Reference: <https://elixir.bootlin.com/linux/v6.18/source/net/core/dev_ioctl.c>
+
+use aster_bigtcp::{
+    iface::{InterfaceFlags, InterfaceType},
+    wire::{Ipv4Address, Ipv4Cidr},
+};
+
+use super::{Iface, iter_all_ifaces};
+use crate::{
+    current_userspace,
+    net::link::set_mtu,
+    prelude::*,
+    process::{credentials::capabilities::CapSet, posix_thread::AsPosixThread},
+    util::{
+        ioctl::{RawIoctl, dispatch_ioctl},
+        net::CSocketAddrFamily,
+    },
+};
+
+/// Handles the ioctl commands that query and configure the ifaces.
+pub(in crate::net) fn iface_ioctl(raw_ioctl: RawIoctl) -> Result<i32> {
+    use ioctl_defs::*;
+
+    dispatch_ioctl!(match raw_ioctl {
+        cmd @ GetIfName => {
+            let mut ifreq = cmd.read()?;
+            let iface = find_iface_by_index(*ifreq.ifru.ifindex())?;
+            ifreq.set_name(iface.name());
+            cmd.write(&ifreq)?;
+        }
+        cmd @ GetIfConf => {
+            let mut ifconf = cmd.read()?;
+            get_iface_conf(&mut ifconf)?;
+            cmd.write(&ifconf)?;
+        }
+        cmd @ GetIfFlags => {
+            let mut ifreq = cmd.read()?;
+            let iface = ifreq.iface()?;
+            // Only the lower 16 bits of the flags are reported, as in Linux.
+            *ifreq.ifru.flags_mut() = iface.flags().bits() as i16;
+            cmd.write(&ifreq)?;
+        }
+        cmd @ GetIfAddr => {
+            let mut ifreq = cmd.read()?;
+            let iface = ifreq.iface()?;
+            let addr = iface.ipv4_addr().ok_or_else(no_ipv4_addr)?;
+            *ifreq.ifru.addr_mut() = CSockAddr::new_inet(addr);
+            cmd.write(&ifreq)?;
+        }
+        cmd @ SetIfAddr => {
+            check_permission()?;
+            let ifreq = cmd.read()?;
+            let iface = ifreq.iface()?;
+            set_ipv4_addr(&iface, ifreq.ifru.addr().to_ipv4()?)?;
+        }
+        cmd @ GetIfBrdAddr => {
+            let mut ifreq = cmd.read()?;
+            let iface = ifreq.iface()?;
+            if iface.ipv4_addr().is_none() {
+                return Err(no_ipv4_addr());
+            }
+            let addr = iface.broadcast_addr().unwrap_or(Ipv4Address::UNSPECIFIED);
+            *ifreq.ifru.addr_mut() = CSockAddr::new_inet(addr);
+            cmd.write(&ifreq)?;
+        }
+        cmd @ GetIfNetmask => {
+            let mut ifreq = cmd.read()?;
+            let iface = ifreq.iface()?;
+            let ipv4_cidr = iface
+                .ipv4_addr()
+                .zip(iface.prefix_len())
+                .map(|(addr, prefix_len)| Ipv4Cidr::new(addr, prefix_len))
+                .ok_or_else(no_ipv4_addr)?;
+            *ifreq.ifru.addr_mut() = CSockAddr::new_inet(ipv4_cidr.netmask());
+            cmd.write(&ifreq)?;
+        }
+        cmd @ GetIfMtu => {
+            let mut ifreq = cmd.read()?;
+            let iface = ifreq.iface()?;
+            *ifreq.ifru.mtu_mut() = iface.mtu() as i32;
+            cmd.write(&ifreq)?;
+        }
+        cmd @ SetIfMtu => {
+            check_permission()?;
+            let ifreq = cmd.read()?;
+            let iface = ifreq.iface()?;
+            let Ok(mtu) = usize::try_from(*ifreq.ifru.mtu()) else {
+                return_errno_with_message!(Errno::EINVAL, "the MTU is negative");
+            };
+            set_mtu(iface.index(), mtu)?;
+        }
+        cmd @ GetIfHwAddr => {
+            let mut ifreq = cmd.read()?;
+            let iface = ifreq.iface()?;
+            // Ifaces without Ethernet addresses (e.g., the loopback iface) report zeros.
+            let ether_addr = iface.ether_addr().map_or([0; 6], |ether_addr| ether_addr.0);
+            *ifreq.ifru.addr_mut() = CSockAddr::new(iface.type_() as u16, &ether_addr);
+            cmd.write(&ifreq)?;
+        }
+        cmd @ GetIfIndex => {
+            let mut ifreq = cmd.read()?;
+            let iface = ifreq.iface()?;
+            *ifreq.ifru.ifindex_mut() = iface.index() as i32;
+            cmd.write(&ifreq)?;
+        }
+        cmd @ Ethtool => {
+            let ifreq = cmd.read()?;
+            let iface = ifreq.iface()?;
+            do_ethtool(&iface, *ifreq.ifru.data())?;
+        }
+        _ => return_errno_with_message!(Errno::ENOTTY, "the ioctl command is unknown"),
+    });
+
+    Ok(0)
+}
+
+mod ioctl_defs {
+    use super::{CIfconf, CIfreq};
+    use crate::util::ioctl::{InData, InOutData, ioc};
+
+    // Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/sockios.h>
+
+    pub(super) type GetIfName    = ioc!(SIOCGIFNAME,    0x8910, InOutData<CIfreq>);
+    pub(super) type GetIfConf    = ioc!(SIOCGIFCONF,    0x8912, InOutData<CIfconf>);
+    pub(super) type GetIfFlags   = ioc!(SIOCGIFFLAGS,   0x8913, InOutData<CIfreq>);
+    pub(super) type GetIfAddr    = ioc!(SIOCGIFADDR,    0x8915, InOutData<CIfreq>);
+    pub(super) type SetIfAddr    = ioc!(SIOCSIFADDR,    0x8916, InData<CIfreq>);
+    pub(super) type GetIfBrdAddr = ioc!(SIOCGIFBRDADDR, 0x8919, InOutData<CIfreq>);
+    pub(super) type GetIfNetmask = ioc!(SIOCGIFNETMASK, 0x891b, InOutData<CIfreq>);
+    pub(super) type GetIfMtu     = ioc!(SIOCGIFMTU,     0x8921, InOutData<CIfreq>);
+    pub(super) type SetIfMtu     = ioc!(SIOCSIFMTU,     0x8922, InData<CIfreq>);
+    pub(super) type GetIfHwAddr  = ioc!(SIOCGIFHWADDR,  0x8927, InOutData<CIfreq>);
+    pub(super) type GetIfIndex   = ioc!(SIOCGIFINDEX,   0x8933, InOutData<CIfreq>);
+    pub(super) type Ethtool      = ioc!(SIOCETHTOOL,    0x8946, InData<CIfreq>);
+}
+
+/// Fills the buffer in `struct ifconf` with the IPv4 addresses of all ifaces.
+///
+/// If the buffer is null, only the length that is required to hold all the addresses is
+/// reported.
+fn get_iface_conf(ifconf: &mut CIfconf) -> Result<()> {
+    let ifreqs: Vec<CIfreq> = iter_all_ifaces()
+        .filter_map(|iface| {
+            let addr = iface.ipv4_addr()?;
+            let mut ifreq = CIfreq::new_zeroed();
+            ifreq.set_name(iface.name());
+            *ifreq.ifru.addr_mut() = CSockAddr::new_inet(addr);
+            Some(ifreq)
+        })
+        .collect();
+
+    if ifconf.buf == 0 {
+        ifconf.len = (ifreqs.len() * size_of::<CIfreq>()) as i32;
+        return Ok(());
+    }
+
+    let max_count = usize::try_from(ifconf.len).unwrap_or(0) / size_of::<CIfreq>();
+    let count = ifreqs.len().min(max_count);
+    for (i, ifreq) in ifreqs[..count].iter().enumerate() {
+        current_userspace!().write_val(ifconf.buf + i * size_of::<CIfreq>(), ifreq)?;
+    }
+    ifconf.len = (count * size_of::<CIfreq>()) as i32;
+
+    Ok(())
+}
+
+/// Replaces the IPv4 address of the iface.
+///
+/// Like Linux, the prefix length is derived from the class of the new address, and the old
+/// address is simply removed if the new address is unspecified.
+fn set_ipv4_addr(iface: &Iface, new_addr: Ipv4Address) -> Result<()> {
+    let Some(prefix_len) = classful_prefix_len(new_addr) else {
+        return_errno_with_message!(Errno::EINVAL, "the address does not belong to any class");
+    };
+
+    if let Some(old_addr) = iface.ipv4_addr() {
+        if old_addr == new_addr {
+            return Ok(());
+        }
+        iface.remove_ipv4_addr(old_addr);
+    }
+
+    if new_addr.is_unspecified() {
+        return Ok(());
+    }
+    if !iface.add_ipv4_addr(Ipv4Cidr::new(new_addr, prefix_len)) {
+        return_errno_with_message!(Errno::ENOSPC, "the interface cannot hold more addresses");
+    }
+
+    Ok(())
+}
+
+/// Returns the prefix length of the classful network that the address belongs to.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/linux/inetdevice.h#L313>.
+fn classful_prefix_len(addr: Ipv4Address) -> Option<u8> {
+    let first_octet = addr.octets()[0];
+
+    if addr.is_unspecified() {
+        Some(0)
+    } else if first_octet & 0x80 == 0 {
+        Some(8)
+    } else if first_octet & 0xc0 == 0x80 {
+        Some(16)
+    } else if first_octet & 0xe0 == 0xc0 {
+        Some(24)
+    } else {
+        None
+    }
+}
+
+/// Handles an ethtool command, whose data is stored at `data_addr`.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/ethtool.h>.
+fn do_ethtool(iface: &Iface, data_addr: Vaddr) -> Result<()> {
+    let cmd: u32 = current_userspace!().read_val(data_addr)?;
+
+    match cmd {
+        ETHTOOL_GLINK => {
+            let value = CEthtoolValue {
+                cmd,
+                data: iface.flags().contains(InterfaceFlags::RUNNING) as u32,
+            };
+            current_userspace!().write_val(data_addr, &value)?;
+        }
+        // The speed and the duplex mode of virtual Ethernet devices are unknown, which is the
+        // same as Linux's virtio-net driver. Other ifaces (e.g., the loopback iface) do not
+        // support this command at all.
+        ETHTOOL_GSET if iface.type_() == InterfaceType::ETHER => {
+            let settings = CEthtoolCmd {
+                cmd,
+                speed: (SPEED_UNKNOWN & 0xffff) as u16,
+                speed_hi: (SPEED_UNKNOWN >> 16) as u16,
+                duplex: DUPLEX_UNKNOWN,
+                port: PORT_OTHER,
+                autoneg: AUTONEG_DISABLE,
+                ..CEthtoolCmd::new_zeroed()
+            };
+            current_userspace!().write_val(data_addr, &settings)?;
+        }
+        _ => return_errno_with_message!(Errno::EOPNOTSUPP, "the ethtool command is not supported"),
+    }
+
+    Ok(())
+}
+
+fn find_iface_by_index(index: i32) -> Result<Arc<Iface>> {
+    iter_all_ifaces()
+        .find(|iface| iface.index() as i32 == index)
+        .ok_or_else(|| Error::with_message(Errno::ENODEV, "the interface does not exist"))
+}
+
+fn no_ipv4_addr() -> Error {
+    Error::with_message(Errno::EADDRNOTAVAIL, "the interface has no IPv4 address")
+}
+
+fn check_permission() -> Result<()> {
+    let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+    if !credentials.effective_capset().contains(CapSet::NET_ADMIN) {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "configuring the interface requires the CAP_NET_ADMIN capability"
+        );
+    }
+
+    Ok(())
+}
+
+const IFNAMSIZ: usize = 16;
+
+/// `struct ifreq`.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/if.h#L234>.
+#[repr(C)]
+#[derive(Clone, Copy, Pod)]
+struct CIfreq {
+    name: [u8; IFNAMSIZ],
+    ifru: CIfreqUnion,
+}
+
+impl CIfreq {
+    fn name(&self) -> Result<&str> {
+        let len = self
+            .name
+            .iter()
+            .position(|byte| *byte == 0)
+            .unwrap_or(IFNAMSIZ)
+            .min(IFNAMSIZ - 1);
+        core::str::from_utf8(&self.name[..len])
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the iface name is invalid"))
+    }
+
+    fn set_name(&mut self, name: &str) {
+        self.name = [0; IFNAMSIZ];
+        self.name[..name.len()].copy_from_slice(name.as_bytes());
+    }
+
+    /// Finds the iface with the name in the request.
+    fn iface(&self) -> Result<Arc<Iface>> {
+        let name = self.name()?;
+        iter_all_ifaces()
+            .find(|iface| iface.name() == name)
+            .ok_or_else(|| Error::with_message(Errno::ENODEV, "the interface does not exist"))
+    }
+}
+
+/// The union in `struct ifreq`.
+#[repr(C)]
+#[pod_union]
+#[derive(Clone, Copy)]
+union CIfreqUnion {
+    addr: CSockAddr,
+    flags: i16,
+    ifindex: i32,
+    mtu: i32,
+    data: Vaddr,
+    /// The space of `struct ifmap`, which is the largest member.
+    map: [u8; 24],
+}
+
+/// `struct sockaddr`, which stores IPv4 addresses or hardware addresses in `struct ifreq`.
+#[repr(C)]
+#[derive(Clone, Copy, Pod)]
+struct CSockAddr {
+    family: u16,
+    data: [u8; 14],
+}
+
+impl CSockAddr {
+    fn new(family: u16, bytes: &[u8]) -> Self {
+        let mut data = [0; 14];
+        data[..bytes.len()].copy_from_slice(bytes);
+        Self { family, data }
+    }
+
+    /// Creates a `struct sockaddr_in` with a zero port.
+    fn new_inet(addr: Ipv4Address) -> Self {
+        let mut data = [0; 14];
+        data[2..6].copy_from_slice(&addr.octets());
+        Self {
+            family: CSocketAddrFamily::AF_INET as u16,
+            data,
+        }
+    }
+
+    fn to_ipv4(self) -> Result<Ipv4Address> {
+        if self.family != CSocketAddrFamily::AF_INET as u16 {
+            return_errno_with_message!(Errno::EINVAL, "the address is not an IPv4 address");
+        }
+
+        Ok(Ipv4Address::new(
+            self.data[2],
+            self.data[3],
+            self.data[4],
+            self.data[5],
+        ))
+    }
+}
+
+/// `struct ifconf`.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/if.h#L285>.
+#[repr(C)]
+#[padding_struct]
+#[derive(Clone, Copy, Pod)]
+struct CIfconf {
+    len: i32,
+    buf: Vaddr,
+}
+
+/// `struct ethtool_value`.
+#[repr(C)]
+#[derive(Clone, Copy, Pod)]
+struct CEthtoolValue {
+    cmd: u32,
+    data: u32,
+}
+
+/// `struct ethtool_cmd`.
+#[repr(C)]
+#[derive(Clone, Copy, Pod)]
+struct CEthtoolCmd {
+    cmd: u32,
+    supported: u32,
+    advertising: u32,
+    speed: u16,
+    duplex: u8,
+    port: u8,
+    phy_address: u8,
+    transceiver: u8,
+    autoneg: u8,
+    mdio_support: u8,
+    maxtxpkt: u32,
+    maxrxpkt: u32,
+    speed_hi: u16,
+    eth_tp_mdix: u8,
+    eth_tp_mdix_ctrl: u8,
+    lp_advertising: u32,
+    reserved: [u32; 2],
+}
+
+const ETHTOOL_GSET: u32 = 0x00000001;
+const ETHTOOL_GLINK: u32 = 0x0000000a;
+
+const SPEED_UNKNOWN: u32 = u32::MAX;
+const DUPLEX_UNKNOWN: u8 = 0xff;
+const PORT_OTHER: u8 = 0xff;
+const AUTONEG_DISABLE: u8 = 0x00;
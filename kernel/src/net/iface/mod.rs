@@ -1,14 +1,21 @@
 // SPDX-License-Identifier: MPL-2.0
 
 mod broadcast;
+mod debugfs;
 mod ext;
 mod init;
+mod ioctl;
 mod poll;
 mod sched;
+mod sysfs;
 
 pub use broadcast::is_broadcast_endpoint;
-pub use init::{init, iter_all_ifaces, loopback_iface, virtio_iface};
-pub(super) use poll::init_in_first_kthread;
+pub use init::{
+    add_iface, alloc_iface_name, init, iter_all_ifaces, loopback_iface, remove_iface, virtio_iface,
+};
+pub(in crate::net) use init::random_ether_addr;
+pub(in crate::net) use ioctl::iface_ioctl;
+pub(in crate::net) use sched::PollScheduler;
 
 pub type Iface = dyn aster_bigtcp::iface::Iface<ext::BigtcpExt>;
 pub type BoundPort = aster_bigtcp::iface::BoundPort<ext::BigtcpExt>;
@@ -18,3 +25,11 @@ pub type RawTcpSocketExt = aster_bigtcp::socket::RawTcpSocketExt<ext::BigtcpExt>
 pub type TcpConnection = aster_bigtcp::socket::TcpConnection<ext::BigtcpExt>;
 pub type TcpListener = aster_bigtcp::socket::TcpListener<ext::BigtcpExt>;
 pub type UdpSocket = aster_bigtcp::socket::UdpSocket<ext::BigtcpExt>;
+pub type RawIpSocket = aster_bigtcp::socket::RawIpSocket<ext::BigtcpExt>;
+pub type PacketSocket = aster_bigtcp::socket::PacketSocket<ext::BigtcpExt>;
+
+pub(super) fn init_in_first_kthread() {
+    poll::init_in_first_kthread();
+    sysfs::init_in_first_kthread();
+    debugfs::init_in_first_kthread();
+}
@@ -15,7 +15,7 @@ use crate::{
 
 pub fn init_in_first_kthread() {
     for iface in iter_all_ifaces() {
-        spawn_background_poll_thread(iface.clone());
+        spawn_background_poll_thread(iface);
     }
 }
 
@@ -25,14 +25,14 @@ pub(super) fn poll_ifaces() {
     }
 }
 
-fn spawn_background_poll_thread(iface: Arc<Iface>) {
+pub(super) fn spawn_background_poll_thread(iface: Arc<Iface>) {
     let task_fn = move || {
         trace!("spawn background poll thread for {}", iface.name());
 
         let sched_poll = iface.sched_poll();
         let wait_queue = sched_poll.polling_wait_queue();
 
-        loop {
+        while !sched_poll.is_stopped() {
             let next_poll_at_ms = if let Some(next_poll_at_ms) = sched_poll.next_poll_at_ms() {
                 next_poll_at_ms
             } else {
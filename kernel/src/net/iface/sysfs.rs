@@ -0,0 +1,77 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Exposes the network interfaces in sysfs (i.e., `/sys/class/net`).
+//!
+//! Reference: <https://www.kernel.org/doc/Documentation/ABI/testing/sysfs-class-net>
+
+use super::{Iface, iter_all_ifaces};
+use crate::{
+    fs::sysfs::{self, ShowFn, SysDevice},
+    prelude::*,
+};
+
+pub(super) fn init_in_first_kthread() {
+    for iface in iter_all_ifaces() {
+        add_iface(&iface);
+    }
+}
+
+/// Adds the iface to sysfs.
+pub(super) fn add_iface(iface: &Arc<Iface>) {
+    if let Err(err) = sysfs::add_device(new_sys_device(iface), None) {
+        warn!(
+            "failed to add the network interface '{}' to sysfs: {:?}",
+            iface.name(),
+            err
+        );
+    }
+}
+
+/// Removes the iface from sysfs.
+pub(super) fn remove_iface(iface: &Arc<Iface>) {
+    if let Err(err) = sysfs::remove_device("net", iface.name(), None, None) {
+        warn!(
+            "failed to remove the network interface '{}' from sysfs: {:?}",
+            iface.name(),
+            err
+        );
+    }
+}
+
+fn new_sys_device(iface: &Arc<Iface>) -> SysDevice {
+    let index = iface.index();
+    let type_ = iface.type_() as u16;
+    let flags = iface.flags().bits();
+    // The MTU of a virtual link can be changed, so it is not cached.
+    let weak_iface = Arc::downgrade(iface);
+
+    let attrs = vec![
+        (
+            "ifindex",
+            Box::new(move || format!("{}\n", index)) as ShowFn,
+        ),
+        ("type", Box::new(move || format!("{}\n", type_)) as ShowFn),
+        (
+            "flags",
+            Box::new(move || format!("{:#x}\n", flags)) as ShowFn,
+        ),
+        (
+            "mtu",
+            Box::new(move || {
+                let mtu = weak_iface.upgrade().map_or(0, |iface| iface.mtu());
+                format!("{}\n", mtu)
+            }) as ShowFn,
+        ),
+    ];
+
+    SysDevice {
+        class: "net",
+        name: iface.name().to_string(),
+        dev: None,
+        uevent_vars: vec![
+            ("INTERFACE", iface.name().to_string()),
+            ("IFINDEX", index.to_string()),
+        ],
+        attrs,
+    }
+}
@@ -0,0 +1,179 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use aster_bigtcp::wire::EthernetAddress;
+use aster_softirq::BottomHalfDisabled;
+use ostd::timer::Jiffies;
+use spin::Once;
+
+use super::device::LinkPort;
+use crate::prelude::*;
+
+/// An 802.1D learning bridge.
+///
+/// The bridge forwards the frames among its ports according to the forwarding database, which
+/// is learned from the source addresses of the received frames. The frames whose destinations are
+/// unknown are flooded to all the ports.
+pub(super) struct Bridge {
+    ether_addr: EthernetAddress,
+    /// The port of the bridge itself, through which the frames are delivered to the bridge iface.
+    local_port: Once<Weak<LinkPort>>,
+    /// The ports enslaved to the bridge.
+    ports: SpinLock<Vec<Arc<LinkPort>>, BottomHalfDisabled>,
+    /// The forwarding database.
+    fdb: SpinLock<BTreeMap<[u8; 6], FdbEntry>, BottomHalfDisabled>,
+}
+
+struct FdbEntry {
+    port: Weak<LinkPort>,
+    updated_at: Duration,
+}
+
+impl Bridge {
+    pub(super) fn new(ether_addr: EthernetAddress) -> Arc<Self> {
+        Arc::new(Self {
+            ether_addr,
+            local_port: Once::new(),
+            ports: SpinLock::new(Vec::new()),
+            fdb: SpinLock::new(BTreeMap::new()),
+        })
+    }
+
+    pub(super) fn set_local_port(&self, local_port: &Arc<LinkPort>) {
+        self.local_port.call_once(|| Arc::downgrade(local_port));
+    }
+
+    /// Enslaves a port to the bridge.
+    pub(super) fn add_port(self: &Arc<Self>, port: Arc<LinkPort>) {
+        port.set_master(Some(self.clone()));
+        self.ports.lock().push(port);
+    }
+
+    /// Releases a port from the bridge.
+    ///
+    /// The forwarding database entries pointing to the port are also removed.
+    pub(super) fn remove_port(&self, port: &Arc<LinkPort>) {
+        self.ports
+            .lock()
+            .retain(|other| !Arc::ptr_eq(other, port));
+        port.set_master(None);
+
+        let port = Arc::downgrade(port);
+        self.fdb
+            .lock()
+            .retain(|_, entry| !Weak::ptr_eq(&entry.port, &port));
+    }
+
+    /// Releases all the ports from the bridge.
+    pub(super) fn remove_all_ports(&self) {
+        let ports = core::mem::take(&mut *self.ports.lock());
+        for port in ports {
+            port.set_master(None);
+        }
+        self.fdb.lock().clear();
+    }
+
+    /// Handles a frame received by one of the ports.
+    pub(super) fn handle_ingress(&self, ingress: &Arc<LinkPort>, frame: Vec<u8>) {
+        let Some((dst, src)) = parse_addrs(&frame) else {
+            return;
+        };
+
+        if src.is_unicast() {
+            self.learn(src, ingress);
+        }
+
+        if dst == self.ether_addr {
+            self.deliver_locally(frame);
+            return;
+        }
+        if !dst.is_unicast() {
+            self.flood(Some(ingress), frame.clone());
+            self.deliver_locally(frame);
+            return;
+        }
+
+        match self.lookup(dst) {
+            // The frame is dropped if it is destined to the port where it comes from.
+            Some(egress) if Arc::ptr_eq(&egress, ingress) => (),
+            Some(egress) => egress.transmit(frame),
+            None => self.flood(Some(ingress), frame),
+        }
+    }
+
+    /// Transmits a frame sent by the bridge iface.
+    pub(super) fn transmit(&self, frame: Vec<u8>) {
+        let Some((dst, _)) = parse_addrs(&frame) else {
+            return;
+        };
+
+        match self.lookup(dst).filter(|_| dst.is_unicast()) {
+            Some(egress) => egress.transmit(frame),
+            None => self.flood(None, frame),
+        }
+    }
+
+    fn learn(&self, addr: EthernetAddress, port: &Arc<LinkPort>) {
+        let now = Jiffies::elapsed().as_duration();
+
+        let mut fdb = self.fdb.lock();
+        if fdb.len() >= MAX_FDB_ENTRIES && !fdb.contains_key(&addr.0) {
+            fdb.retain(|_, entry| now - entry.updated_at < FDB_AGEING_TIME);
+            if fdb.len() >= MAX_FDB_ENTRIES {
+                return;
+            }
+        }
+        fdb.insert(
+            addr.0,
+            FdbEntry {
+                port: Arc::downgrade(port),
+                updated_at: now,
+            },
+        );
+    }
+
+    fn lookup(&self, addr: EthernetAddress) -> Option<Arc<LinkPort>> {
+        let now = Jiffies::elapsed().as_duration();
+
+        let mut fdb = self.fdb.lock();
+        let entry = fdb.get(&addr.0)?;
+        if now - entry.updated_at >= FDB_AGEING_TIME {
+            fdb.remove(&addr.0);
+            return None;
+        }
+        entry.port.upgrade()
+    }
+
+    /// Sends a frame to all the ports except the ingress port.
+    fn flood(&self, ingress: Option<&Arc<LinkPort>>, frame: Vec<u8>) {
+        let ports = self.ports.lock().clone();
+        for port in ports {
+            if ingress.is_some_and(|ingress| Arc::ptr_eq(ingress, &port)) {
+                continue;
+            }
+            port.transmit(frame.clone());
+        }
+    }
+
+    fn deliver_locally(&self, frame: Vec<u8>) {
+        if let Some(local_port) = self.local_port.get().and_then(Weak::upgrade) {
+            local_port.receive(frame);
+        }
+    }
+}
+
+/// The time after which an entry in the forwarding database expires.
+///
+/// This is the default value of `ageing_time` of bridges in Linux.
+const FDB_AGEING_TIME: Duration = Duration::from_secs(300);
+
+/// The maximum number of entries in the forwarding database.
+const MAX_FDB_ENTRIES: usize = 4096;
+
+/// Returns the destination address and the source address of the Ethernet frame.
+fn parse_addrs(frame: &[u8]) -> Option<(EthernetAddress, EthernetAddress)> {
+    let dst = EthernetAddress::from_bytes(frame.get(0..6)?);
+    let src = EthernetAddress::from_bytes(frame.get(6..12)?);
+    Some((dst, src))
+}
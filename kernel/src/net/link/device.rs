@@ -0,0 +1,433 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::collections::VecDeque;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use aster_bigtcp::{
+    device::{self, DeviceCapabilities, Medium, NotifyDevice, WithDevice},
+    time::Instant,
+};
+use aster_softirq::BottomHalfDisabled;
+use spin::Once;
+
+use super::bridge::Bridge;
+use crate::{
+    net::iface::Iface,
+    prelude::*,
+    thread::work_queue::{WorkPriority, submit_work_item, work_item::WorkItem},
+};
+
+/// The port of a virtual link, which moves the Ethernet frames between the link and others.
+pub(super) struct LinkPort {
+    kind: PortKind,
+    /// The frames that have not been received by the iface.
+    rx_frames: SpinLock<VecDeque<Vec<u8>>, BottomHalfDisabled>,
+    /// The work item that polls the iface.
+    ///
+    /// The iface cannot be polled when a frame is delivered, because the delivery may happen
+    /// while another iface (or even the same iface) is being polled.
+    poll_work: Once<Arc<WorkItem>>,
+    /// The bridge that the link is enslaved to.
+    master: SpinLock<Option<Arc<Bridge>>, BottomHalfDisabled>,
+    /// The VLAN links on top of the link, indexed by their VLAN IDs.
+    vlans: SpinLock<BTreeMap<u16, Weak<LinkPort>>, BottomHalfDisabled>,
+    mtu: AtomicUsize,
+    /// The maximum number of the frames that are transmitted by a veth link but have not been
+    /// received by its peer.
+    tx_queue_len: AtomicUsize,
+    /// Whether the transmission is stopped because the transmit queue is full.
+    ///
+    /// The iface will be polled again once the peer receives the queued frames.
+    is_tx_stopped: AtomicBool,
+}
+
+pub(super) enum PortKind {
+    /// A port of a veth link, whose peer is the other end of the veth pair.
+    Veth { peer: Weak<LinkPort> },
+    /// The local port of a bridge.
+    Bridge(Arc<Bridge>),
+    /// A port of a VLAN link, which inserts and strips the VLAN tags for the parent link.
+    Vlan { parent: Arc<LinkPort>, id: u16 },
+}
+
+impl LinkPort {
+    /// Creates the ports of both ends of a veth pair.
+    pub(super) fn new_veth_pair() -> (Arc<Self>, Arc<Self>) {
+        let mut peer_port = None;
+        let port = Arc::new_cyclic(|port| {
+            let peer = Arc::new(Self::new(PortKind::Veth { peer: port.clone() }, MTU));
+            let this = Self::new(
+                PortKind::Veth {
+                    peer: Arc::downgrade(&peer),
+                },
+                MTU,
+            );
+            peer_port = Some(peer);
+            this
+        });
+        (port, peer_port.unwrap())
+    }
+
+    /// Creates the local port of a bridge.
+    pub(super) fn new_bridge(bridge: Arc<Bridge>) -> Arc<Self> {
+        Arc::new(Self::new(PortKind::Bridge(bridge), MTU))
+    }
+
+    /// Creates the port of a VLAN link on top of the parent link.
+    ///
+    /// This method fails with [`Errno::EEXIST`] if the parent link already has a VLAN link with
+    /// the same VLAN ID.
+    pub(super) fn new_vlan(parent: Arc<LinkPort>, id: u16) -> Result<Arc<Self>> {
+        let mut vlans = parent.vlans.lock();
+        if vlans.contains_key(&id) {
+            return_errno_with_message!(Errno::EEXIST, "the VLAN ID is already in use");
+        }
+
+        let mtu = parent.mtu();
+        let port = Arc::new(Self::new(
+            PortKind::Vlan {
+                parent: parent.clone(),
+                id,
+            },
+            mtu,
+        ));
+        vlans.insert(id, Arc::downgrade(&port));
+
+        Ok(port)
+    }
+
+    fn new(kind: PortKind, mtu: usize) -> Self {
+        Self {
+            kind,
+            rx_frames: SpinLock::new(VecDeque::new()),
+            poll_work: Once::new(),
+            master: SpinLock::new(None),
+            vlans: SpinLock::new(BTreeMap::new()),
+            mtu: AtomicUsize::new(mtu),
+            tx_queue_len: AtomicUsize::new(DEFAULT_TX_QUEUE_LEN),
+            is_tx_stopped: AtomicBool::new(false),
+        }
+    }
+
+    /// Binds the port to its iface.
+    pub(super) fn bind_iface(&self, iface: &Arc<Iface>) {
+        let iface = Arc::downgrade(iface);
+        let poll_work = WorkItem::new(Box::new(move || {
+            if let Some(iface) = iface.upgrade() {
+                iface.poll();
+            }
+        }));
+        self.poll_work.call_once(|| poll_work);
+    }
+
+    pub(super) fn kind(&self) -> &PortKind {
+        &self.kind
+    }
+
+    pub(super) fn master(&self) -> Option<Arc<Bridge>> {
+        self.master.lock().clone()
+    }
+
+    pub(super) fn set_master(&self, master: Option<Arc<Bridge>>) {
+        *self.master.lock() = master;
+    }
+
+    /// Unregisters the port from its parent link if the port is a port of a VLAN link.
+    pub(super) fn unregister_vlan(&self) {
+        if let PortKind::Vlan { parent, id } = &self.kind {
+            parent.vlans.lock().remove(id);
+        }
+    }
+
+    pub(super) fn mtu(&self) -> usize {
+        self.mtu.load(Ordering::Relaxed)
+    }
+
+    /// Sets the MTU of the link.
+    ///
+    /// The MTU of a VLAN link cannot exceed the MTU of its parent link. If the MTU of a parent
+    /// link becomes smaller, the MTUs of its VLAN links are reduced accordingly.
+    pub(super) fn set_mtu(&self, mtu: usize) -> Result<()> {
+        let max_mtu = match &self.kind {
+            PortKind::Vlan { parent, .. } => parent.mtu(),
+            PortKind::Veth { .. } | PortKind::Bridge(_) => MAX_MTU,
+        };
+        if !(MIN_MTU..=max_mtu).contains(&mtu) {
+            return_errno_with_message!(Errno::EINVAL, "the MTU is out of range");
+        }
+
+        self.mtu.store(mtu, Ordering::Relaxed);
+
+        for vlan in self.vlans() {
+            if vlan.mtu() > mtu {
+                vlan.set_mtu(mtu).unwrap();
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn tx_queue_len(&self) -> usize {
+        self.tx_queue_len.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn set_tx_queue_len(&self, tx_queue_len: usize) {
+        self.tx_queue_len.store(tx_queue_len, Ordering::Relaxed);
+    }
+
+    /// Transmits a frame sent by the iface (or forwarded by the bridge that the link is enslaved
+    /// to).
+    pub(super) fn transmit(&self, frame: Vec<u8>) {
+        match &self.kind {
+            PortKind::Veth { peer } => {
+                if let Some(peer) = peer.upgrade() {
+                    peer.receive(frame);
+                }
+            }
+            PortKind::Bridge(bridge) => bridge.transmit(frame),
+            PortKind::Vlan { parent, id } => {
+                if frame.len() < ETHERNET_HEADER_LEN {
+                    return;
+                }
+
+                let mut tagged_frame = Vec::with_capacity(frame.len() + VLAN_HEADER_LEN);
+                tagged_frame.extend_from_slice(&frame[..ETHERNET_ADDRS_LEN]);
+                tagged_frame.extend_from_slice(&ETH_P_8021Q.to_be_bytes());
+                tagged_frame.extend_from_slice(&id.to_be_bytes());
+                tagged_frame.extend_from_slice(&frame[ETHERNET_ADDRS_LEN..]);
+                parent.transmit(tagged_frame);
+            }
+        }
+    }
+
+    /// Returns whether the link can transmit a frame now.
+    ///
+    /// If this method returns `false`, the transmission is stopped until the peer receives the
+    /// queued frames.
+    fn can_transmit(&self) -> bool {
+        match &self.kind {
+            PortKind::Veth { peer } => {
+                let Some(peer) = peer.upgrade() else {
+                    return true;
+                };
+
+                let rx_frames = peer.rx_frames.lock();
+                if rx_frames.len() < self.tx_queue_len() {
+                    return true;
+                }
+                // The flag is set with the peer's queue locked, so the peer will see the flag
+                // after it receives the queued frames.
+                self.is_tx_stopped.store(true, Ordering::Relaxed);
+                false
+            }
+            // The frames are flooded to multiple ports, so they are not subject to flow control.
+            PortKind::Bridge(_) => true,
+            // The VLAN link shares the transmit queue with its parent link.
+            PortKind::Vlan { parent, .. } => parent.can_transmit(),
+        }
+    }
+
+    /// Resumes the transmission by polling the iface and the ifaces of its VLAN links.
+    fn wake_tx(&self) {
+        self.submit_poll();
+        for vlan in self.vlans() {
+            vlan.wake_tx();
+        }
+    }
+
+    /// Receives a frame from the peer (or from the parent link or the bridge).
+    ///
+    /// The frame is handled by the VLAN link if it is tagged with the ID of a VLAN link on top of
+    /// the link. Otherwise, if the link is enslaved to a bridge, the frame is handled by the
+    /// bridge instead of the iface.
+    pub(super) fn receive(self: &Arc<Self>, mut frame: Vec<u8>) {
+        let vlan = parse_vlan_id(&frame).and_then(|id| self.vlans.lock().get(&id)?.upgrade());
+        if let Some(vlan) = vlan {
+            frame.drain(ETHERNET_ADDRS_LEN..ETHERNET_ADDRS_LEN + VLAN_HEADER_LEN);
+            vlan.receive(frame);
+            return;
+        }
+
+        if let Some(master) = self.master() {
+            master.handle_ingress(self, frame);
+        } else {
+            self.deliver(frame);
+        }
+    }
+
+    /// Delivers a frame to the iface.
+    ///
+    /// Like Linux, the frame is dropped if it exceeds the MTU. It is also dropped if too many
+    /// frames have not been received by the iface.
+    fn deliver(&self, frame: Vec<u8>) {
+        let max_len = if parse_vlan_id(&frame).is_some() {
+            self.mtu() + ETHERNET_HEADER_LEN + VLAN_HEADER_LEN
+        } else {
+            self.mtu() + ETHERNET_HEADER_LEN
+        };
+        if frame.len() > max_len {
+            return;
+        }
+
+        {
+            let mut rx_frames = self.rx_frames.lock();
+            if rx_frames.len() >= MAX_QUEUED_FRAMES {
+                return;
+            }
+            rx_frames.push_back(frame);
+        }
+
+        self.submit_poll();
+    }
+
+    /// Takes a frame that has not been received by the iface.
+    fn pop_rx_frame(&self) -> Option<Vec<u8>> {
+        let frame = self.rx_frames.lock().pop_front()?;
+
+        if let PortKind::Veth { peer } = &self.kind {
+            let stopped_peer = peer
+                .upgrade()
+                .filter(|peer| peer.is_tx_stopped.swap(false, Ordering::Relaxed));
+            if let Some(peer) = stopped_peer {
+                peer.wake_tx();
+            }
+        }
+
+        Some(frame)
+    }
+
+    fn submit_poll(&self) {
+        if let Some(poll_work) = self.poll_work.get() {
+            submit_work_item(poll_work.clone(), WorkPriority::High);
+        }
+    }
+
+    fn vlans(&self) -> Vec<Arc<LinkPort>> {
+        self.vlans
+            .lock()
+            .values()
+            .filter_map(Weak::upgrade)
+            .collect()
+    }
+}
+
+/// The maximum number of frames that have not been received by an iface.
+///
+/// This is the default value of `netdev_max_backlog` in Linux.
+const MAX_QUEUED_FRAMES: usize = 1000;
+
+/// The default length of the transmit queue.
+const DEFAULT_TX_QUEUE_LEN: usize = 1000;
+
+/// The default MTU of virtual links.
+const MTU: usize = 1500;
+
+/// The minimum MTU of Ethernet links (`ETH_MIN_MTU`).
+const MIN_MTU: usize = 68;
+
+/// The maximum MTU of virtual links.
+///
+/// Larger frames cannot be represented by smoltcp.
+const MAX_MTU: usize = 65535 - ETHERNET_HEADER_LEN;
+
+const ETHERNET_HEADER_LEN: usize = 14;
+
+/// The length of the destination address and the source address in an Ethernet header.
+const ETHERNET_ADDRS_LEN: usize = 12;
+
+const VLAN_HEADER_LEN: usize = 4;
+
+/// The EtherType of 802.1Q VLAN tags.
+const ETH_P_8021Q: u16 = 0x8100;
+
+/// Returns the VLAN ID if the frame has an 802.1Q VLAN tag.
+fn parse_vlan_id(frame: &[u8]) -> Option<u16> {
+    let header = frame.get(ETHERNET_ADDRS_LEN..ETHERNET_ADDRS_LEN + VLAN_HEADER_LEN)?;
+    if u16::from_be_bytes([header[0], header[1]]) != ETH_P_8021Q {
+        return None;
+    }
+    // The VLAN ID is the lower 12 bits of the tag control information.
+    Some(u16::from_be_bytes([header[2], header[3]]) & 0x0FFF)
+}
+
+/// The device of a virtual link.
+pub(super) struct LinkDevice(Arc<LinkPort>);
+
+impl LinkDevice {
+    pub(super) fn new(port: Arc<LinkPort>) -> Self {
+        Self(port)
+    }
+}
+
+impl device::Device for LinkDevice {
+    type RxToken<'a> = RxToken;
+    type TxToken<'a> = TxToken<'a>;
+
+    fn receive(&mut self, _timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        let frame = self.0.pop_rx_frame()?;
+        Some((RxToken(frame), TxToken(self)))
+    }
+
+    fn transmit(&mut self, _timestamp: Instant) -> Option<Self::TxToken<'_>> {
+        if !self.0.can_transmit() {
+            return None;
+        }
+        Some(TxToken(self))
+    }
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        let mut caps = DeviceCapabilities::default();
+        caps.medium = Medium::Ethernet;
+        caps.max_transmission_unit = self.0.mtu() + ETHERNET_HEADER_LEN;
+        caps
+    }
+}
+
+impl NotifyDevice for LinkDevice {
+    fn notify_poll_end(&mut self) {}
+}
+
+pub(super) struct RxToken(Vec<u8>);
+
+impl device::RxToken for RxToken {
+    fn consume<R, F>(self, f: F) -> R
+    where
+        F: FnOnce(&[u8]) -> R,
+    {
+        f(&self.0)
+    }
+}
+
+pub(super) struct TxToken<'a>(&'a LinkDevice);
+
+impl device::TxToken for TxToken<'_> {
+    fn consume<R, F>(self, len: usize, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        let mut frame = vec![0u8; len];
+        let res = f(&mut frame);
+        self.0.0.transmit(frame);
+        res
+    }
+}
+
+pub(super) struct Wrapper(Mutex<LinkDevice>);
+
+impl Wrapper {
+    pub(super) fn new(device: LinkDevice) -> Self {
+        Self(Mutex::new(device))
+    }
+}
+
+impl WithDevice for Wrapper {
+    type Device = LinkDevice;
+
+    fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Self::Device) -> R,
+    {
+        let mut device = self.0.lock();
+        f(&mut device)
+    }
+}
@@ -0,0 +1,365 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Virtual links created via rtnetlink.
+//!
+//! Three kinds of virtual links are supported:
+//!  - A veth pair consists of two links, where the frames sent by one link are received by the
+//!    other link.
+//!  - A bridge forwards the frames among the links enslaved to it (see [`set_master`]).
+//!  - A VLAN link inserts 802.1Q VLAN tags into the frames sent through its parent link, and
+//!    receives the frames with its VLAN ID from the parent link after stripping the tags.
+//!
+//! Only veth links and VLAN links can be enslaved to bridges, and only virtual links can be the
+//! parents of VLAN links for now.
+
+mod bridge;
+mod device;
+
+use alloc::borrow::ToOwned;
+
+use aster_bigtcp::{
+    iface::{EtherIface, InterfaceFlags},
+    wire::EthernetAddress,
+};
+
+use self::{
+    bridge::Bridge,
+    device::{LinkDevice, LinkPort, PortKind, Wrapper},
+};
+use super::iface::{
+    Iface, PollScheduler, add_iface, alloc_iface_name, random_ether_addr, remove_iface,
+};
+use crate::prelude::*;
+
+/// The virtual links, indexed by the indexes of their ifaces.
+static LINKS: Mutex<BTreeMap<u32, Link>> = Mutex::new(BTreeMap::new());
+
+struct Link {
+    iface: Arc<Iface>,
+    port: Arc<LinkPort>,
+}
+
+/// The kind of a virtual link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    Veth,
+    Bridge,
+    Vlan,
+}
+
+impl LinkKind {
+    /// Returns the name of the kind, which is used in `IFLA_INFO_KIND`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Veth => "veth",
+            Self::Bridge => "bridge",
+            Self::Vlan => "vlan",
+        }
+    }
+}
+
+/// The details of a virtual link.
+#[derive(Debug, Clone, Copy)]
+pub struct LinkDetails {
+    pub kind: LinkKind,
+    /// The index of the other end of the veth pair, or the index of the parent link of the VLAN
+    /// link.
+    pub link: Option<u32>,
+    /// The index of the bridge that the link is enslaved to.
+    pub master: Option<u32>,
+    /// The VLAN ID of the VLAN link.
+    pub vlan_id: Option<u16>,
+    pub tx_queue_len: u32,
+}
+
+/// Creates a veth pair.
+///
+/// If a name is not specified, it is allocated from the template `veth%d`. This function returns
+/// the index of the first link.
+pub fn new_veth_pair(name: Option<&str>, peer_name: Option<&str>) -> Result<u32> {
+    let mut links = LINKS.lock();
+
+    let (port, peer_port) = LinkPort::new_veth_pair();
+
+    let iface = new_iface(alloc_name(name, "veth%d")?, random_ether_addr(), &port);
+    add_iface(iface.clone())?;
+
+    let peer_iface = match alloc_name(peer_name, "veth%d") {
+        Ok(peer_name) => new_iface(peer_name, random_ether_addr(), &peer_port),
+        Err(err) => {
+            remove_iface(&iface);
+            return Err(err);
+        }
+    };
+    if let Err(err) = add_iface(peer_iface.clone()) {
+        remove_iface(&iface);
+        return Err(err);
+    }
+
+    let index = iface.index();
+    links.insert(index, Link { iface, port });
+    links.insert(
+        peer_iface.index(),
+        Link {
+            iface: peer_iface,
+            port: peer_port,
+        },
+    );
+
+    Ok(index)
+}
+
+/// Creates a bridge.
+///
+/// If the name is not specified, it is allocated from the template `bridge%d`. This function
+/// returns the index of the link.
+pub fn new_bridge(name: Option<&str>) -> Result<u32> {
+    let mut links = LINKS.lock();
+
+    let ether_addr = random_ether_addr();
+    let bridge = Bridge::new(ether_addr);
+    let port = LinkPort::new_bridge(bridge.clone());
+    bridge.set_local_port(&port);
+
+    let iface = new_iface(alloc_name(name, "bridge%d")?, ether_addr, &port);
+    add_iface(iface.clone())?;
+
+    let index = iface.index();
+    links.insert(index, Link { iface, port });
+
+    Ok(index)
+}
+
+/// Creates a VLAN link on top of the parent link.
+///
+/// If the name is not specified, it is allocated from the template `vlan%d`. Like Linux, the VLAN
+/// link has the same Ethernet address as its parent link. This function returns the index of the
+/// link.
+pub fn new_vlan(name: Option<&str>, parent_index: u32, vlan_id: u16) -> Result<u32> {
+    /// The maximum VLAN ID. VLAN ID 4095 is reserved.
+    const MAX_VLAN_ID: u16 = 4094;
+
+    if vlan_id > MAX_VLAN_ID {
+        return_errno_with_message!(Errno::ERANGE, "the VLAN ID is out of range");
+    }
+
+    let mut links = LINKS.lock();
+
+    let Some(parent) = links.get(&parent_index) else {
+        return_errno_with_message!(
+            Errno::EOPNOTSUPP,
+            "only virtual links can be the parents of VLAN links"
+        );
+    };
+    let ether_addr = parent.iface.ether_addr().unwrap();
+    let name = alloc_name(name, "vlan%d")?;
+
+    let port = LinkPort::new_vlan(parent.port.clone(), vlan_id)?;
+    let iface = new_iface(name, ether_addr, &port);
+    if let Err(err) = add_iface(iface.clone()) {
+        port.unregister_vlan();
+        return Err(err);
+    }
+
+    let index = iface.index();
+    links.insert(index, Link { iface, port });
+
+    Ok(index)
+}
+
+/// Deletes a virtual link.
+///
+/// Deleting either end of a veth pair deletes both ends. Deleting a bridge releases all the links
+/// enslaved to it. Deleting a link also deletes the VLAN links on top of it.
+pub fn delete_link(index: u32) -> Result<()> {
+    let mut links = LINKS.lock();
+
+    let Some(link) = links.remove(&index) else {
+        return_errno_with_message!(Errno::EOPNOTSUPP, "the link is not a virtual link");
+    };
+
+    let mut deleted_links = vec![link];
+    if let PortKind::Veth { peer } = deleted_links[0].port.kind() {
+        let peer_index = peer.upgrade().and_then(|peer| find_index(&links, &peer));
+        if let Some(peer_link) = peer_index.and_then(|peer_index| links.remove(&peer_index)) {
+            deleted_links.push(peer_link);
+        }
+    }
+
+    // The loop also visits the VLAN links pushed in previous iterations, so the VLAN links on top
+    // of other VLAN links are deleted as well.
+    let mut i = 0;
+    while i < deleted_links.len() {
+        let parent_port = deleted_links[i].port.clone();
+        let vlan_indexes = links
+            .iter()
+            .filter(|(_, link)| match link.port.kind() {
+                PortKind::Vlan { parent, .. } => Arc::ptr_eq(parent, &parent_port),
+                PortKind::Veth { .. } | PortKind::Bridge(_) => false,
+            })
+            .map(|(index, _)| *index)
+            .collect::<Vec<_>>();
+        for vlan_index in vlan_indexes {
+            deleted_links.push(links.remove(&vlan_index).unwrap());
+        }
+        i += 1;
+    }
+
+    for link in deleted_links.iter() {
+        release_link(link);
+    }
+
+    Ok(())
+}
+
+/// Enslaves a link to the bridge with the master index, or releases the link from its bridge if
+/// the master index is `None`.
+pub fn set_master(index: u32, master_index: Option<u32>) -> Result<()> {
+    let links = LINKS.lock();
+
+    let Some(link) = links
+        .get(&index)
+        .filter(|link| !matches!(link.port.kind(), PortKind::Bridge(_)))
+    else {
+        return_errno_with_message!(
+            Errno::EOPNOTSUPP,
+            "only veth links and VLAN links can be enslaved to bridges"
+        );
+    };
+
+    let new_master = if let Some(master_index) = master_index {
+        let Some(PortKind::Bridge(bridge)) = links.get(&master_index).map(|link| link.port.kind())
+        else {
+            return_errno_with_message!(Errno::EINVAL, "the master link is not a bridge");
+        };
+        Some(bridge.clone())
+    } else {
+        None
+    };
+
+    let old_master = link.port.master();
+    match (old_master, new_master) {
+        (Some(old_master), Some(new_master)) if Arc::ptr_eq(&old_master, &new_master) => (),
+        (old_master, new_master) => {
+            if let Some(old_master) = old_master {
+                old_master.remove_port(&link.port);
+            }
+            if let Some(new_master) = new_master {
+                new_master.add_port(link.port.clone());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Sets the MTU of a virtual link.
+pub fn set_mtu(index: u32, mtu: usize) -> Result<()> {
+    let links = LINKS.lock();
+
+    let Some(link) = links.get(&index) else {
+        return_errno_with_message!(
+            Errno::EOPNOTSUPP,
+            "changing the MTU of the link is not supported"
+        );
+    };
+
+    link.port.set_mtu(mtu)
+}
+
+/// Sets the length of the transmit queue of a virtual link.
+pub fn set_tx_queue_len(index: u32, tx_queue_len: u32) -> Result<()> {
+    let links = LINKS.lock();
+
+    let Some(link) = links.get(&index) else {
+        return_errno_with_message!(
+            Errno::EOPNOTSUPP,
+            "changing the transmit queue length of the link is not supported"
+        );
+    };
+
+    link.port.set_tx_queue_len(tx_queue_len as usize);
+
+    Ok(())
+}
+
+/// Returns the details of the link, or `None` if the link is not a virtual link.
+pub fn link_details(index: u32) -> Option<LinkDetails> {
+    let links = LINKS.lock();
+    let link = links.get(&index)?;
+
+    let (kind, link_index, vlan_id) = match link.port.kind() {
+        PortKind::Veth { peer } => {
+            let peer_index = peer.upgrade().and_then(|peer| find_index(&links, &peer));
+            (LinkKind::Veth, peer_index, None)
+        }
+        PortKind::Bridge(_) => (LinkKind::Bridge, None, None),
+        PortKind::Vlan { parent, id } => (LinkKind::Vlan, find_index(&links, parent), Some(*id)),
+    };
+
+    let master = link.port.master().and_then(|master| {
+        links.iter().find_map(|(index, other)| match other.port.kind() {
+            PortKind::Bridge(bridge) if Arc::ptr_eq(bridge, &master) => Some(*index),
+            _ => None,
+        })
+    });
+
+    Some(LinkDetails {
+        kind,
+        link: link_index,
+        master,
+        vlan_id,
+        tx_queue_len: link.port.tx_queue_len() as u32,
+    })
+}
+
+fn new_iface(name: String, ether_addr: EthernetAddress, port: &Arc<LinkPort>) -> Arc<Iface> {
+    let flags = InterfaceFlags::UP
+        | InterfaceFlags::BROADCAST
+        | InterfaceFlags::RUNNING
+        | InterfaceFlags::MULTICAST
+        | InterfaceFlags::LOWER_UP;
+
+    let iface = EtherIface::new(
+        Wrapper::new(LinkDevice::new(port.clone())),
+        ether_addr,
+        None,
+        None,
+        name,
+        PollScheduler::new(),
+        flags,
+    ) as Arc<Iface>;
+    port.bind_iface(&iface);
+
+    iface
+}
+
+/// Allocates the name of a new iface.
+///
+/// If the name is not specified or contains `%d`, the name is allocated from the template.
+pub(super) fn alloc_name(name: Option<&str>, template: &str) -> Result<String> {
+    match name {
+        Some(name) if name.contains("%d") => alloc_iface_name(name),
+        Some(name) => Ok(name.to_owned()),
+        None => alloc_iface_name(template),
+    }
+}
+
+fn find_index(links: &BTreeMap<u32, Link>, port: &Arc<LinkPort>) -> Option<u32> {
+    links
+        .iter()
+        .find_map(|(index, link)| Arc::ptr_eq(&link.port, port).then_some(*index))
+}
+
+/// Releases the link from its bridge (and its parent link) and removes its iface.
+fn release_link(link: &Link) {
+    match link.port.kind() {
+        PortKind::Bridge(bridge) => bridge.remove_all_ports(),
+        PortKind::Vlan { .. } => link.port.unregister_vlan(),
+        PortKind::Veth { .. } => (),
+    }
+    if let Some(master) = link.port.master() {
+        master.remove_port(&link.port);
+    }
+    remove_iface(&link.iface);
+}
@@ -1,10 +1,15 @@
 // SPDX-License-Identifier: MPL-2.0
 
 pub mod iface;
+pub mod link;
+pub mod netfilter;
 pub mod socket;
+pub mod tun;
 pub mod uts_ns;
+pub mod wireguard;
 
 pub fn init() {
+    netfilter::init();
     iface::init();
     socket::netlink::init();
     socket::vsock::init();
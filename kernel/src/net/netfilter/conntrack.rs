@@ -0,0 +1,522 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Connection tracking.
+//!
+//! A connection is identified by two tuples. The original tuple is the tuple of the packets sent
+//! by the side that starts the connection, and the reply tuple is the tuple of the packets sent
+//! by the other side. If the connection is translated, the reply tuple is the inverse of the
+//! translated original tuple, so the replies can be translated back.
+//!
+//! Currently, only IPv4 TCP and UDP connections are tracked.
+
+use core::time::Duration;
+
+use aster_bigtcp::{
+    filter::{Hook, PacketInfo, TcpFlags, Verdict},
+    wire::{IpAddress, IpProtocol, Ipv4Address},
+};
+use aster_softirq::BottomHalfDisabled;
+use ostd::{sync::SpinLock, timer::Jiffies};
+
+use super::{
+    nat::{Manip, NatTarget},
+    table::{Decision, IptTable},
+};
+use crate::prelude::*;
+
+/// The connection tracking table.
+pub(super) struct ConnTrack {
+    inner: SpinLock<ConnTrackInner, BottomHalfDisabled>,
+}
+
+struct ConnTrackInner {
+    conns: BTreeMap<u64, Connection>,
+    /// The IDs of the connections indexed by both the original tuples and the reply tuples.
+    tuples: BTreeMap<Tuple, u64>,
+    next_id: u64,
+}
+
+impl ConnTrack {
+    pub(super) const fn new() -> Self {
+        Self {
+            inner: SpinLock::new(ConnTrackInner {
+                conns: BTreeMap::new(),
+                tuples: BTreeMap::new(),
+                next_id: 1,
+            }),
+        }
+    }
+
+    /// Tracks the packet at the hook and translates it if its connection is translated.
+    ///
+    /// If the packet starts a new connection, the `nat_table` decides how the connection is
+    /// translated.
+    pub(super) fn process(
+        &self,
+        hook: Hook,
+        packet: &mut PacketInfo,
+        nat_table: &IptTable,
+    ) -> Verdict {
+        let Some(manip) = Manip::of_hook(hook) else {
+            return Verdict::Accept;
+        };
+
+        let mut inner = self.inner.lock();
+
+        // The packet is tracked at the first hook that it goes through, which is either
+        // `Hook::PreRouting` or `Hook::Output`. The result is kept in `packet.conntrack` for the
+        // later hooks.
+        if packet.conntrack == 0 {
+            if manip != Manip::Dst {
+                return Verdict::Accept;
+            }
+
+            let now = Jiffies::elapsed().as_duration();
+            match inner.track(packet, now) {
+                Ok(Some(conntrack)) => packet.conntrack = conntrack,
+                Ok(None) => return Verdict::Accept,
+                Err(_) => return Verdict::Drop,
+            }
+        }
+        let (id, dir) = decode_conntrack(packet.conntrack);
+
+        let Some(conn) = inner.conns.get_mut(&id) else {
+            // The connection has been removed while the packet goes through the hooks.
+            return Verdict::Accept;
+        };
+        if dir == Direction::Original && !conn.nat_done(manip) {
+            conn.set_nat_done(manip);
+            match nat_table.evaluate(hook, packet) {
+                Decision::Verdict(Verdict::Accept) => (),
+                Decision::Verdict(Verdict::Drop) => return Verdict::Drop,
+                Decision::Nat(nat_target) => {
+                    if !inner.setup_nat(id, hook, manip, &nat_target, packet) {
+                        return Verdict::Drop;
+                    }
+                }
+            }
+        }
+
+        inner.conns[&id].translate(dir, manip, packet);
+        Verdict::Accept
+    }
+}
+
+impl ConnTrackInner {
+    /// Tracks the packet and returns its connection tracking state.
+    ///
+    /// This method returns `None` if the packet is not tracked.
+    fn track(&mut self, packet: &PacketInfo, now: Duration) -> Result<Option<u64>> {
+        let Some(tuple) = Tuple::from_packet(packet) else {
+            return Ok(None);
+        };
+
+        let existing = match self.tuples.get(&tuple) {
+            Some(id) if self.conns[id].expires_at > now => Some(*id),
+            Some(id) => {
+                let id = *id;
+                self.remove(id);
+                None
+            }
+            None => None,
+        };
+
+        let (id, dir) = if let Some(id) = existing {
+            let dir = if self.conns[&id].original == tuple {
+                Direction::Original
+            } else {
+                Direction::Reply
+            };
+            (id, dir)
+        } else {
+            let Some(state) = ProtocolState::new(tuple.protocol, packet.tcp_flags) else {
+                return Ok(None);
+            };
+            (self.insert(tuple, state, now)?, Direction::Original)
+        };
+
+        let conn = self.conns.get_mut(&id).unwrap();
+        conn.update(dir, packet.tcp_flags, now);
+
+        Ok(Some(encode_conntrack(id, dir)))
+    }
+
+    fn insert(&mut self, tuple: Tuple, state: ProtocolState, now: Duration) -> Result<u64> {
+        let reply = tuple.invert();
+        if self.tuples.contains_key(&reply) {
+            return_errno_with_message!(Errno::EEXIST, "the reply tuple is in use");
+        }
+
+        if self.conns.len() >= MAX_CONNS {
+            self.remove_expired(now);
+            if self.conns.len() >= MAX_CONNS {
+                return_errno_with_message!(Errno::ENOSPC, "too many connections are tracked");
+            }
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.conns.insert(
+            id,
+            Connection {
+                original: tuple,
+                reply,
+                state,
+                src_nat_done: false,
+                dst_nat_done: false,
+                expires_at: now,
+            },
+        );
+        self.tuples.insert(tuple, id);
+        self.tuples.insert(reply, id);
+
+        Ok(id)
+    }
+
+    fn remove(&mut self, id: u64) {
+        let Some(conn) = self.conns.remove(&id) else {
+            return;
+        };
+        self.tuples.remove(&conn.original);
+        self.tuples.remove(&conn.reply);
+    }
+
+    fn remove_expired(&mut self, now: Duration) {
+        let expired_ids: Vec<_> = self
+            .conns
+            .iter()
+            .filter(|(_, conn)| conn.expires_at <= now)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in expired_ids {
+            self.remove(id);
+        }
+    }
+
+    /// Sets up the translation of the part of the connection with the NAT target.
+    ///
+    /// The new port is chosen so that the reply tuple is unique. This method returns `false` if
+    /// the connection cannot be translated.
+    fn setup_nat(
+        &mut self,
+        id: u64,
+        hook: Hook,
+        manip: Manip,
+        nat_target: &NatTarget,
+        packet: &PacketInfo,
+    ) -> bool {
+        let conn = &self.conns[&id];
+        let original = conn.original;
+        let old_reply = conn.reply;
+
+        // The tuple of the packets in the original direction after the previous translation.
+        let translated = old_reply.invert();
+        let (addr, port) = match manip {
+            Manip::Src => (translated.src_addr, translated.src_port),
+            Manip::Dst => (translated.dst_addr, translated.dst_port),
+        };
+        let Some(new_addr) = nat_target.new_addr(hook, packet, addr) else {
+            return false;
+        };
+
+        for new_port in nat_target.new_ports(manip, port) {
+            let mut new_translated = translated;
+            match manip {
+                Manip::Src => {
+                    new_translated.src_addr = new_addr;
+                    new_translated.src_port = new_port;
+                }
+                Manip::Dst => {
+                    new_translated.dst_addr = new_addr;
+                    new_translated.dst_port = new_port;
+                }
+            }
+
+            let new_reply = new_translated.invert();
+            if self.tuples.get(&new_reply).is_some_and(|other| *other != id) {
+                continue;
+            }
+
+            if old_reply != original {
+                self.tuples.remove(&old_reply);
+            }
+            self.tuples.insert(new_reply, id);
+            self.conns.get_mut(&id).unwrap().reply = new_reply;
+            return true;
+        }
+
+        false
+    }
+}
+
+/// The maximum number of the tracked connections.
+const MAX_CONNS: usize = 65536;
+
+fn encode_conntrack(id: u64, dir: Direction) -> u64 {
+    (id << 1) | dir as u64
+}
+
+fn decode_conntrack(conntrack: u64) -> (u64, Direction) {
+    let dir = if conntrack & 1 == 0 {
+        Direction::Original
+    } else {
+        Direction::Reply
+    };
+    (conntrack >> 1, dir)
+}
+
+/// The addresses, the ports, and the protocol of a packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Tuple {
+    src_addr: Ipv4Address,
+    src_port: u16,
+    dst_addr: Ipv4Address,
+    dst_port: u16,
+    protocol: IpProtocol,
+}
+
+impl Tuple {
+    fn from_packet(packet: &PacketInfo) -> Option<Self> {
+        let (IpAddress::Ipv4(src_addr), IpAddress::Ipv4(dst_addr)) =
+            (packet.src_addr, packet.dst_addr)
+        else {
+            return None;
+        };
+        if !matches!(packet.protocol, IpProtocol::Tcp | IpProtocol::Udp) {
+            return None;
+        }
+        let (src_port, dst_port) = packet.ports?;
+
+        Some(Self {
+            src_addr,
+            src_port,
+            dst_addr,
+            dst_port,
+            protocol: packet.protocol,
+        })
+    }
+
+    /// Returns the tuple of the packets in the opposite direction.
+    fn invert(&self) -> Self {
+        Self {
+            src_addr: self.dst_addr,
+            src_port: self.dst_port,
+            dst_addr: self.src_addr,
+            dst_port: self.src_port,
+            protocol: self.protocol,
+        }
+    }
+}
+
+/// The direction of a packet in a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    /// From the side that starts the connection.
+    Original = 0,
+    /// From the other side.
+    Reply = 1,
+}
+
+/// A tracked connection.
+struct Connection {
+    original: Tuple,
+    reply: Tuple,
+    state: ProtocolState,
+    /// Whether the translation of the sources has been decided.
+    src_nat_done: bool,
+    /// Whether the translation of the destinations has been decided.
+    dst_nat_done: bool,
+    expires_at: Duration,
+}
+
+impl Connection {
+    fn nat_done(&self, manip: Manip) -> bool {
+        match manip {
+            Manip::Src => self.src_nat_done,
+            Manip::Dst => self.dst_nat_done,
+        }
+    }
+
+    fn set_nat_done(&mut self, manip: Manip) {
+        match manip {
+            Manip::Src => self.src_nat_done = true,
+            Manip::Dst => self.dst_nat_done = true,
+        }
+    }
+
+    fn update(&mut self, dir: Direction, tcp_flags: TcpFlags, now: Duration) {
+        let timeout = match &mut self.state {
+            ProtocolState::Tcp(tcp_state) => {
+                tcp_state.update(dir, tcp_flags);
+                tcp_state.timeout()
+            }
+            ProtocolState::Udp { replied } => {
+                *replied |= dir == Direction::Reply;
+                if *replied {
+                    UDP_STREAM_TIMEOUT
+                } else {
+                    UDP_TIMEOUT
+                }
+            }
+        };
+        self.expires_at = now + timeout;
+    }
+
+    /// Translates the part of the packet in the direction.
+    ///
+    /// The packet is translated so that its tuple becomes the inverse of the tuple in the other
+    /// direction. Translating a packet more than once has no effect.
+    fn translate(&self, dir: Direction, manip: Manip, packet: &mut PacketInfo) {
+        let target = match dir {
+            Direction::Original => self.reply.invert(),
+            Direction::Reply => self.original.invert(),
+        };
+        let Some((src_port, dst_port)) = packet.ports else {
+            return;
+        };
+
+        match manip {
+            Manip::Src => {
+                packet.src_addr = IpAddress::Ipv4(target.src_addr);
+                packet.ports = Some((target.src_port, dst_port));
+            }
+            Manip::Dst => {
+                packet.dst_addr = IpAddress::Ipv4(target.dst_addr);
+                packet.ports = Some((src_port, target.dst_port));
+            }
+        }
+    }
+}
+
+/// The protocol-specific state of a connection.
+enum ProtocolState {
+    Tcp(TcpState),
+    Udp {
+        /// Whether a packet has been seen in the reply direction.
+        replied: bool,
+    },
+}
+
+impl ProtocolState {
+    /// Creates the state for a new connection.
+    ///
+    /// This method returns `None` if the packet should not start a connection.
+    fn new(protocol: IpProtocol, tcp_flags: TcpFlags) -> Option<Self> {
+        match protocol {
+            IpProtocol::Tcp => TcpState::new(tcp_flags).map(Self::Tcp),
+            _ => Some(Self::Udp { replied: false }),
+        }
+    }
+}
+
+/// The state of a TCP connection.
+///
+/// The state is a simplified version of the state in Linux, which does not check the sequence
+/// numbers or the windows.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/net/netfilter/nf_conntrack_proto_tcp.c>.
+struct TcpState {
+    phase: TcpPhase,
+    /// Whether a FIN has been seen in each direction.
+    fin_seen: [bool; 2],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TcpPhase {
+    SynSent,
+    SynRecv,
+    Established,
+    FinWait,
+    CloseWait,
+    LastAck,
+    TimeWait,
+    Close,
+}
+
+impl TcpState {
+    fn new(tcp_flags: TcpFlags) -> Option<Self> {
+        // Like Linux, the connections that have been established before they are seen are
+        // picked up, but RSTs never start connections.
+        let phase = if tcp_flags.contains(TcpFlags::RST) {
+            return None;
+        } else if tcp_flags.contains(TcpFlags::SYN) && !tcp_flags.contains(TcpFlags::ACK) {
+            TcpPhase::SynSent
+        } else {
+            TcpPhase::Established
+        };
+
+        Some(Self {
+            phase,
+            fin_seen: [false; 2],
+        })
+    }
+
+    fn update(&mut self, dir: Direction, tcp_flags: TcpFlags) {
+        use TcpPhase::*;
+
+        if tcp_flags.contains(TcpFlags::RST) {
+            self.phase = Close;
+            return;
+        }
+
+        let has_ack = tcp_flags.contains(TcpFlags::ACK);
+        if tcp_flags.contains(TcpFlags::SYN) {
+            match (dir, has_ack, self.phase) {
+                // A new SYN may reopen a closed connection.
+                (Direction::Original, false, SynSent | TimeWait | Close) => {
+                    self.phase = SynSent;
+                    self.fin_seen = [false; 2];
+                }
+                (Direction::Reply, true, SynSent) => self.phase = SynRecv,
+                _ => (),
+            }
+            return;
+        }
+
+        if self.phase == SynRecv && dir == Direction::Original && has_ack {
+            self.phase = Established;
+        }
+        if !matches!(self.phase, Established | FinWait | CloseWait | LastAck) {
+            return;
+        }
+
+        let this_side = dir as usize;
+        let other_side = 1 - this_side;
+        if tcp_flags.contains(TcpFlags::FIN) {
+            self.fin_seen[this_side] = true;
+            self.phase = if self.fin_seen[other_side] {
+                LastAck
+            } else {
+                FinWait
+            };
+        } else if has_ack && self.fin_seen[other_side] {
+            // The ACK acknowledges the FIN from the other side.
+            self.phase = if self.fin_seen[this_side] {
+                TimeWait
+            } else {
+                CloseWait
+            };
+        }
+    }
+
+    fn timeout(&self) -> Duration {
+        // Reference: <https://elixir.bootlin.com/linux/v6.13/source/net/netfilter/nf_conntrack_proto_tcp.c#L70>.
+        let secs = match self.phase {
+            TcpPhase::SynSent => 120,
+            TcpPhase::SynRecv => 60,
+            TcpPhase::Established => 5 * 24 * 60 * 60,
+            TcpPhase::FinWait => 120,
+            TcpPhase::CloseWait => 60,
+            TcpPhase::LastAck => 30,
+            TcpPhase::TimeWait => 120,
+            TcpPhase::Close => 10,
+        };
+        Duration::from_secs(secs)
+    }
+}
+
+// Timeouts of UDP connections.
+// Reference: <https://elixir.bootlin.com/linux/v6.13/source/net/netfilter/nf_conntrack_proto_udp.c#L26>.
+const UDP_TIMEOUT: Duration = Duration::from_secs(30);
+const UDP_STREAM_TIMEOUT: Duration = Duration::from_secs(120);
@@ -0,0 +1,123 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Packet filtering and network address translation (NAT).
+//!
+//! The IP stack calls the packet filter at the netfilter hooks (i.e., prerouting, input, forward,
+//! output, and postrouting). The packets are filtered by the rules in the `filter` table, and the
+//! connections are translated by the rules in the `nat` table. Both tables can be configured by
+//! user space (e.g., `iptables-legacy`) with the iptables socket options.
+//!
+//! Currently, only the IPv4 tables are supported. Their rules can match the addresses, the
+//! ifaces, the protocol, and the TCP/UDP ports, and can accept, drop, translate (with `SNAT`,
+//! `DNAT`, `MASQUERADE`, and `REDIRECT`), or jump to other chains.
+
+mod conntrack;
+mod nat;
+mod sockopt;
+mod table;
+
+use aster_bigtcp::{
+    filter::{Hook, PacketFilter, PacketInfo, Verdict},
+    wire::IpAddress,
+};
+use ostd::sync::Rcu;
+use spin::Once;
+
+use self::{
+    conntrack::ConnTrack,
+    table::{Decision, IptTable, TableKind},
+};
+use crate::{
+    prelude::*,
+    trace::events::net::{trace_net_dev_xmit, trace_netif_receive_skb},
+};
+
+pub use sockopt::{get_iptables_option, is_iptables_option, set_iptables_option};
+
+// FIXME: The tables and the connections should be per network namespace.
+static TABLES: Once<Tables> = Once::new();
+
+/// The lock that serializes the updates of [`TABLES`].
+static TABLE_UPDATE_LOCK: Mutex<()> = Mutex::new(());
+
+static CONNTRACK: ConnTrack = ConnTrack::new();
+
+struct Tables {
+    filter: Rcu<Box<IptTable>>,
+    nat: Rcu<Box<IptTable>>,
+}
+
+impl Tables {
+    fn get(&self, kind: TableKind) -> &Rcu<Box<IptTable>> {
+        match kind {
+            TableKind::Filter => &self.filter,
+            TableKind::Nat => &self.nat,
+        }
+    }
+}
+
+pub(super) fn init() {
+    TABLES.call_once(|| Tables {
+        filter: Rcu::new(Box::new(IptTable::new(TableKind::Filter))),
+        nat: Rcu::new(Box::new(IptTable::new(TableKind::Nat))),
+    });
+}
+
+/// The packet filter that filters and translates packets by the iptables rules.
+pub struct IptablesFilter;
+
+impl PacketFilter for IptablesFilter {
+    fn filter(hook: Hook, packet: &mut PacketInfo) -> Verdict {
+        trace_packet(hook, packet);
+
+        let Some(tables) = TABLES.get() else {
+            return Verdict::Accept;
+        };
+        let filter_table = tables.filter.read();
+        let nat_table = tables.nat.read();
+
+        let filter = |packet: &PacketInfo| match filter_table.get().evaluate(hook, packet) {
+            Decision::Verdict(verdict) => verdict,
+            // The `filter` table never contains NAT targets.
+            Decision::Nat(_) => Verdict::Accept,
+        };
+
+        // Like Linux, the destinations are translated before the packets are filtered, and the
+        // sources are translated after the packets are filtered.
+        match hook {
+            Hook::PreRouting | Hook::Output => {
+                if CONNTRACK.process(hook, packet, nat_table.get()) == Verdict::Drop {
+                    return Verdict::Drop;
+                }
+                filter(packet)
+            }
+            Hook::Input | Hook::Forward | Hook::PostRouting => {
+                if filter(packet) == Verdict::Drop {
+                    return Verdict::Drop;
+                }
+                CONNTRACK.process(hook, packet, nat_table.get())
+            }
+        }
+    }
+}
+
+/// Fires the tracepoints for the packets that are received or about to be sent.
+fn trace_packet(hook: Hook, packet: &PacketInfo) {
+    if !matches!(hook, Hook::PreRouting | Hook::PostRouting) {
+        return;
+    }
+    // The addresses of IPv6 packets do not fit in the arguments of the tracepoints.
+    let (IpAddress::Ipv4(src_addr), IpAddress::Ipv4(dst_addr)) = (packet.src_addr, packet.dst_addr)
+    else {
+        return;
+    };
+
+    let ifindex = packet.iface.index;
+    let protocol = packet.protocol.into();
+    let (src_port, dst_port) = packet.ports.unwrap_or((0, 0));
+    if hook == Hook::PreRouting {
+        trace_netif_receive_skb(ifindex, protocol, src_addr, src_port, dst_addr, dst_port);
+    } else {
+        trace_net_dev_xmit(ifindex, protocol, src_addr, src_port, dst_addr, dst_port);
+    }
+}
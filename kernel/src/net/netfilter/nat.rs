@@ -0,0 +1,176 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The targets of network address translation (NAT).
+//!
+//! The NAT targets are only used in the `nat` table. The table is only consulted for the first
+//! packet of each connection, and the decision is remembered by connection tracking, which
+//! translates the other packets of the connection in the same way.
+
+use aster_bigtcp::{
+    filter::{Hook, PacketInfo},
+    wire::Ipv4Address,
+};
+
+use super::sockopt::CNfNatIpv4MultiRangeCompat;
+use crate::prelude::*;
+
+/// A NAT target.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct NatTarget {
+    kind: NatKind,
+    /// The range of the new addresses, or `None` if the addresses are not specified.
+    addrs: Option<(Ipv4Address, Ipv4Address)>,
+    /// The range of the new ports, or `None` if the ports are not specified.
+    ports: Option<(u16, u16)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NatKind {
+    /// Translates the source to the specified address (`SNAT`).
+    Snat,
+    /// Translates the destination to the specified address (`DNAT`).
+    Dnat,
+    /// Translates the source to the address of the outgoing iface (`MASQUERADE`).
+    Masquerade,
+    /// Translates the destination to the address of the local host (`REDIRECT`).
+    Redirect,
+}
+
+/// The part of a packet that is translated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Manip {
+    /// The source address and the source port.
+    Src,
+    /// The destination address and the destination port.
+    Dst,
+}
+
+impl Manip {
+    /// Returns the part that is translated at the hook.
+    pub(super) fn of_hook(hook: Hook) -> Option<Self> {
+        match hook {
+            Hook::PreRouting | Hook::Output => Some(Self::Dst),
+            Hook::Input | Hook::PostRouting => Some(Self::Src),
+            Hook::Forward => None,
+        }
+    }
+}
+
+impl NatTarget {
+    /// Parses a target if it is a NAT target.
+    ///
+    /// This method returns `None` if the target is not a NAT target.
+    pub(super) fn parse(name: &[u8], data: &[u8]) -> Result<Option<Self>> {
+        let kind = match name {
+            b"SNAT" => NatKind::Snat,
+            b"DNAT" => NatKind::Dnat,
+            b"MASQUERADE" => NatKind::Masquerade,
+            b"REDIRECT" => NatKind::Redirect,
+            _ => return Ok(None),
+        };
+
+        if data.len() < size_of::<CNfNatIpv4MultiRangeCompat>() {
+            return_errno_with_message!(Errno::EINVAL, "the NAT target is truncated");
+        }
+        let multi_range = CNfNatIpv4MultiRangeCompat::from_first_bytes(data);
+        if multi_range.rangesize != 1 {
+            return_errno_with_message!(Errno::EINVAL, "the NAT target must have one range");
+        }
+        let range = &multi_range.range[0];
+
+        let addrs = if range.flags & NF_NAT_RANGE_MAP_IPS == 0 {
+            None
+        } else {
+            match kind {
+                NatKind::Snat | NatKind::Dnat => (),
+                NatKind::Masquerade => {
+                    return_errno_with_message!(
+                        Errno::EINVAL,
+                        "the MASQUERADE target cannot specify addresses"
+                    );
+                }
+                // Like Linux, the addresses are ignored.
+                NatKind::Redirect => (),
+            }
+            let min_addr = Ipv4Address::from(range.min_ip);
+            let max_addr = Ipv4Address::from(range.max_ip);
+            if min_addr > max_addr {
+                return_errno_with_message!(Errno::EINVAL, "the address range is invalid");
+            }
+            (kind != NatKind::Redirect).then_some((min_addr, max_addr))
+        };
+
+        let ports = if range.flags & NF_NAT_RANGE_PROTO_SPECIFIED == 0 {
+            None
+        } else {
+            let min_port = u16::from_be(range.min);
+            let max_port = u16::from_be(range.max);
+            if min_port > max_port {
+                return_errno_with_message!(Errno::EINVAL, "the port range is invalid");
+            }
+            Some((min_port, max_port))
+        };
+
+        Ok(Some(Self { kind, addrs, ports }))
+    }
+
+    /// Returns the hooks where the target can be used.
+    pub(super) fn valid_hooks(&self) -> u32 {
+        const PRE_ROUTING: u32 = 1 << 0;
+        const LOCAL_IN: u32 = 1 << 1;
+        const LOCAL_OUT: u32 = 1 << 3;
+        const POST_ROUTING: u32 = 1 << 4;
+
+        match self.kind {
+            NatKind::Snat => POST_ROUTING | LOCAL_IN,
+            NatKind::Dnat | NatKind::Redirect => PRE_ROUTING | LOCAL_OUT,
+            NatKind::Masquerade => POST_ROUTING,
+        }
+    }
+
+    /// Returns the new address of the translated part of the packet at the hook.
+    ///
+    /// `addr` is the current address of the translated part. This method returns `None` if the
+    /// packet cannot be translated (e.g., the iface has no IPv4 address).
+    pub(super) fn new_addr(
+        &self,
+        hook: Hook,
+        packet: &PacketInfo,
+        addr: Ipv4Address,
+    ) -> Option<Ipv4Address> {
+        match self.kind {
+            // TODO: Distribute the connections among the addresses in the range.
+            NatKind::Snat | NatKind::Dnat => Some(self.addrs.map_or(addr, |(min, _)| min)),
+            NatKind::Masquerade => packet.iface.ipv4_addr,
+            // Like Linux, the locally generated packets are redirected to the loopback address.
+            NatKind::Redirect if hook == Hook::Output => Some(Ipv4Address::LOCALHOST),
+            NatKind::Redirect => packet.iface.ipv4_addr,
+        }
+    }
+
+    /// Returns the candidates of the new port of the translated part of the packet.
+    ///
+    /// `port` is the current port of the translated part, which is preferred if it is allowed.
+    pub(super) fn new_ports(&self, manip: Manip, port: u16) -> impl Iterator<Item = u16> {
+        // Like Linux, if no ports are specified, the source port may be changed to another port
+        // of the same class to keep the connection unique, but the destination port is kept.
+        // Reference: <https://elixir.bootlin.com/linux/v6.13/source/net/netfilter/nf_nat_proto.c#L37>.
+        let (min, max) = match (self.ports, manip) {
+            (Some(range), _) => range,
+            (None, Manip::Src) if port < 512 => (1, 511),
+            (None, Manip::Src) if port < 1024 => (600, 1023),
+            (None, Manip::Src) => (1024, u16::MAX),
+            (None, Manip::Dst) => (port, port),
+        };
+
+        let preferred = (min..=max).contains(&port).then_some(port);
+        preferred
+            .into_iter()
+            .chain((min..=max).filter(move |other| Some(*other) != preferred))
+    }
+}
+
+// Flags of `struct nf_nat_ipv4_range`.
+// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/netfilter/nf_nat.h#L8>.
+const NF_NAT_RANGE_MAP_IPS: u32 = 1 << 0;
+const NF_NAT_RANGE_PROTO_SPECIFIED: u32 = 1 << 1;
@@ -0,0 +1,364 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The socket options of iptables.
+//!
+//! Unlike other socket options, the iptables options share the same names between `getsockopt`
+//! and `setsockopt` (e.g., `IPT_SO_SET_REPLACE` and `IPT_SO_GET_INFO`), and some of them read
+//! input from the option values in `getsockopt` (e.g., `IPT_SO_GET_ENTRIES`). Therefore, they are
+//! handled here instead of being modeled as [`SocketOption`]s.
+//!
+//! [`SocketOption`]: crate::net::socket::options::SocketOption
+
+use super::{
+    TABLE_UPDATE_LOCK, TABLES,
+    table::{IptTable, NUM_HOOKS, TableKind},
+};
+use crate::{
+    current_userspace,
+    prelude::*,
+    process::{credentials::capabilities::CapSet, posix_thread::AsPosixThread},
+};
+
+/// Returns whether the IP-level socket option is an iptables option.
+pub fn is_iptables_option(name: i32) -> bool {
+    (IPT_BASE_CTL..=IPT_SO_GET_REVISION_TARGET).contains(&name)
+}
+
+/// Sets an iptables option.
+pub fn set_iptables_option(name: i32, optval: Vaddr, optlen: u32) -> Result<()> {
+    check_permission()?;
+
+    match name {
+        IPT_SO_SET_REPLACE => do_replace(optval, optlen),
+        // TODO: Support packet and byte counters.
+        IPT_SO_SET_ADD_COUNTERS => Ok(()),
+        _ => return_errno_with_message!(Errno::EINVAL, "the iptables option cannot be set"),
+    }
+}
+
+/// Gets an iptables option.
+pub fn get_iptables_option(name: i32, optval: Vaddr, optlen: u32) -> Result<()> {
+    check_permission()?;
+
+    match name {
+        IPT_SO_GET_INFO => do_get_info(optval, optlen),
+        IPT_SO_GET_ENTRIES => do_get_entries(optval, optlen),
+        IPT_SO_GET_REVISION_MATCH => do_get_revision(optval, optlen, &[b"tcp", b"udp"]),
+        IPT_SO_GET_REVISION_TARGET => do_get_revision(
+            optval,
+            optlen,
+            &[b"", b"ERROR", b"SNAT", b"DNAT", b"MASQUERADE", b"REDIRECT"],
+        ),
+        _ => return_errno_with_message!(Errno::EINVAL, "the iptables option cannot be got"),
+    }
+}
+
+fn check_permission() -> Result<()> {
+    let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+    if !credentials.effective_capset().contains(CapSet::NET_ADMIN) {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "configuring iptables requires the CAP_NET_ADMIN capability"
+        );
+    }
+
+    Ok(())
+}
+
+fn do_replace(optval: Vaddr, optlen: u32) -> Result<()> {
+    if (optlen as usize) < size_of::<CIptReplace>() {
+        return_errno_with_message!(Errno::EINVAL, "the option length is too short");
+    }
+
+    let user_space = current_userspace!();
+    let replace = user_space.read_val::<CIptReplace>(optval)?;
+    if replace.num_counters == 0 {
+        return_errno_with_message!(Errno::EINVAL, "the number of counters is zero");
+    }
+    if replace.size as usize > MAX_TABLE_SIZE {
+        return_errno_with_message!(Errno::ENOMEM, "the table is too large");
+    }
+    if optlen as usize != size_of::<CIptReplace>() + replace.size as usize {
+        return_errno_with_message!(Errno::ENOPROTOOPT, "the option length is invalid");
+    }
+
+    let kind = parse_table_name(&replace.name)?;
+    if replace.valid_hooks != kind.valid_hooks() {
+        return_errno_with_message!(Errno::EINVAL, "the hooks do not match the table");
+    }
+
+    let mut entries = vec![0u8; replace.size as usize];
+    user_space.read_bytes(optval + size_of::<CIptReplace>(), &mut entries)?;
+    let new_table = IptTable::parse(
+        kind,
+        replace.hook_entry,
+        replace.underflow,
+        replace.num_entries,
+        entries,
+    )?;
+
+    let _guard = TABLE_UPDATE_LOCK.lock();
+    let table = TABLES.get().unwrap().get(kind);
+
+    // Like Linux, the number of counters must be the number of entries in the old table, which
+    // prevents the table from being replaced by multiple processes at the same time.
+    let old_num_entries = table.read().get().num_entries();
+    if replace.num_counters != old_num_entries {
+        return_errno_with_message!(Errno::EAGAIN, "the table has been changed");
+    }
+
+    // The counters of the old table are reported back to user space. Counters are not
+    // supported, so they are always zero.
+    let counters = vec![0u8; replace.num_counters as usize * size_of::<CXtCounters>()];
+    user_space.write_bytes(replace.counters as Vaddr, &counters)?;
+
+    table.update(Box::new(new_table));
+
+    Ok(())
+}
+
+fn do_get_info(optval: Vaddr, optlen: u32) -> Result<()> {
+    if optlen as usize != size_of::<CIptGetinfo>() {
+        return_errno_with_message!(Errno::EINVAL, "the option length is invalid");
+    }
+
+    let user_space = current_userspace!();
+    let mut info = user_space.read_val::<CIptGetinfo>(optval)?;
+    let kind = parse_table_name(&info.name)?;
+
+    let table_guard = TABLES.get().unwrap().get(kind).read();
+    let table = table_guard.get();
+    info.valid_hooks = table.valid_hooks();
+    info.hook_entry = table.hook_entry();
+    info.underflow = table.underflow();
+    info.num_entries = table.num_entries();
+    info.size = table.entries().len() as u32;
+
+    user_space.write_val(optval, &info)
+}
+
+fn do_get_entries(optval: Vaddr, optlen: u32) -> Result<()> {
+    if (optlen as usize) < size_of::<CIptGetEntries>() {
+        return_errno_with_message!(Errno::EINVAL, "the option length is too short");
+    }
+
+    let user_space = current_userspace!();
+    let get_entries = user_space.read_val::<CIptGetEntries>(optval)?;
+    if optlen as usize != size_of::<CIptGetEntries>() + get_entries.size as usize {
+        return_errno_with_message!(Errno::EINVAL, "the option length is invalid");
+    }
+    let kind = parse_table_name(&get_entries.name)?;
+
+    // The entries are copied so that the user space is not accessed in the RCU read-side
+    // critical section.
+    let entries = {
+        let table = TABLES.get().unwrap().get(kind).read();
+        table.get().entries().to_vec()
+    };
+    if entries.len() != get_entries.size as usize {
+        return_errno_with_message!(Errno::EAGAIN, "the table has been changed");
+    }
+
+    user_space.write_bytes(optval + size_of::<CIptGetEntries>(), &entries)
+}
+
+fn do_get_revision(optval: Vaddr, optlen: u32, supported_names: &[&[u8]]) -> Result<()> {
+    if optlen as usize != size_of::<CXtGetRevision>() {
+        return_errno_with_message!(Errno::EINVAL, "the option length is invalid");
+    }
+
+    let revision = current_userspace!().read_val::<CXtGetRevision>(optval)?;
+    let name_len = revision
+        .name
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(revision.name.len());
+    if !supported_names.contains(&&revision.name[..name_len]) {
+        return_errno_with_message!(Errno::ENOENT, "the extension is not supported");
+    }
+    if revision.revision != 0 {
+        return_errno_with_message!(Errno::EPROTONOSUPPORT, "the revision is not supported");
+    }
+
+    Ok(())
+}
+
+fn parse_table_name(name: &[u8; XT_TABLE_MAXNAMELEN]) -> Result<TableKind> {
+    // TODO: Support other tables (e.g., `mangle` and `raw`).
+    let name_len = name.iter().position(|byte| *byte == 0).unwrap_or(name.len());
+    TableKind::from_name(&name[..name_len])
+        .ok_or_else(|| Error::with_message(Errno::ENOENT, "the table does not exist"))
+}
+
+/// The maximum size of the entries in a table.
+const MAX_TABLE_SIZE: usize = 16 * 1024 * 1024;
+
+// Socket options of iptables.
+// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/netfilter_ipv4/ip_tables.h#L129>.
+const IPT_BASE_CTL: i32 = 64;
+const IPT_SO_SET_REPLACE: i32 = IPT_BASE_CTL;
+const IPT_SO_SET_ADD_COUNTERS: i32 = IPT_BASE_CTL + 1;
+const IPT_SO_GET_INFO: i32 = IPT_BASE_CTL;
+const IPT_SO_GET_ENTRIES: i32 = IPT_BASE_CTL + 1;
+const IPT_SO_GET_REVISION_MATCH: i32 = IPT_BASE_CTL + 2;
+const IPT_SO_GET_REVISION_TARGET: i32 = IPT_BASE_CTL + 3;
+
+const XT_TABLE_MAXNAMELEN: usize = 32;
+const XT_EXTENSION_MAXNAMELEN: usize = 29;
+pub(super) const IFNAMSIZ: usize = 16;
+
+/// `ipt_getinfo` in Linux.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/netfilter_ipv4/ip_tables.h#L145>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CIptGetinfo {
+    name: [u8; XT_TABLE_MAXNAMELEN],
+    valid_hooks: u32,
+    hook_entry: [u32; NUM_HOOKS],
+    underflow: [u32; NUM_HOOKS],
+    num_entries: u32,
+    size: u32,
+}
+
+/// `ipt_replace` in Linux.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/netfilter_ipv4/ip_tables.h#L167>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CIptReplace {
+    name: [u8; XT_TABLE_MAXNAMELEN],
+    valid_hooks: u32,
+    num_entries: u32,
+    size: u32,
+    hook_entry: [u32; NUM_HOOKS],
+    underflow: [u32; NUM_HOOKS],
+    num_counters: u32,
+    counters: u64,
+}
+
+/// `ipt_get_entries` in Linux, without the entries.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/netfilter_ipv4/ip_tables.h#L199>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CIptGetEntries {
+    name: [u8; XT_TABLE_MAXNAMELEN],
+    size: u32,
+    _pad: u32,
+}
+
+/// `xt_counters` in Linux.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/netfilter/x_tables.h#L103>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct CXtCounters {
+    pub(super) pcnt: u64,
+    pub(super) bcnt: u64,
+}
+
+/// `xt_get_revision` in Linux.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/netfilter/x_tables.h#L114>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CXtGetRevision {
+    name: [u8; XT_EXTENSION_MAXNAMELEN],
+    revision: u8,
+}
+
+/// `ipt_ip` in Linux.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/netfilter_ipv4/ip_tables.h#L68>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Pod)]
+pub(super) struct CIptIp {
+    pub(super) src: [u8; 4],
+    pub(super) dst: [u8; 4],
+    pub(super) smsk: [u8; 4],
+    pub(super) dmsk: [u8; 4],
+    pub(super) iniface: [u8; IFNAMSIZ],
+    pub(super) outiface: [u8; IFNAMSIZ],
+    pub(super) iniface_mask: [u8; IFNAMSIZ],
+    pub(super) outiface_mask: [u8; IFNAMSIZ],
+    pub(super) proto: u16,
+    pub(super) flags: u8,
+    pub(super) invflags: u8,
+}
+
+/// `ipt_entry` in Linux, without the matches and the target.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/netfilter_ipv4/ip_tables.h#L106>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct CIptEntry {
+    pub(super) ip: CIptIp,
+    pub(super) nfcache: u32,
+    pub(super) target_offset: u16,
+    pub(super) next_offset: u16,
+    pub(super) comefrom: u32,
+    pub(super) counters: CXtCounters,
+}
+
+/// The header of `xt_entry_match` and `xt_entry_target` in Linux.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/netfilter/x_tables.h#L11>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct CXtEntryHeader {
+    pub(super) size: u16,
+    pub(super) name: [u8; XT_EXTENSION_MAXNAMELEN],
+    pub(super) revision: u8,
+}
+
+/// `xt_tcp` in Linux.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/netfilter/xt_tcpudp.h#L8>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct CXtTcp {
+    pub(super) spts: [u16; 2],
+    pub(super) dpts: [u16; 2],
+    pub(super) option: u8,
+    pub(super) flg_mask: u8,
+    pub(super) flg_cmp: u8,
+    pub(super) invflags: u8,
+}
+
+/// `xt_udp` in Linux.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/netfilter/xt_tcpudp.h#L25>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct CXtUdp {
+    pub(super) spts: [u16; 2],
+    pub(super) dpts: [u16; 2],
+    pub(super) invflags: u8,
+    pub(super) _pad: u8,
+}
+
+/// `nf_nat_ipv4_range` in Linux.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/netfilter/nf_nat.h#L28>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct CNfNatIpv4Range {
+    pub(super) flags: u32,
+    pub(super) min_ip: [u8; 4],
+    pub(super) max_ip: [u8; 4],
+    /// The minimum port in network byte order.
+    pub(super) min: u16,
+    /// The maximum port in network byte order.
+    pub(super) max: u16,
+}
+
+/// `nf_nat_ipv4_multi_range_compat` in Linux.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/netfilter/nf_nat.h#L35>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct CNfNatIpv4MultiRangeCompat {
+    pub(super) rangesize: u32,
+    pub(super) range: [CNfNatIpv4Range; 1],
+}
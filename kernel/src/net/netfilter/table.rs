@@ -0,0 +1,683 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The tables of iptables.
+//!
+//! A table consists of the entries in the format of user space, which are parsed into rules. The
+//! rules are organized into chains. Each built-in chain starts at the entry of a hook and ends
+//! with the policy of the hook, while user-defined chains are only reachable by jumps.
+
+use aster_bigtcp::{
+    filter::{Hook, PacketInfo, Verdict},
+    wire::{IpAddress, IpProtocol, Ipv4Address},
+};
+
+use super::{
+    nat::NatTarget,
+    sockopt::{CIptEntry, CIptIp, CXtEntryHeader, CXtTcp, CXtUdp, IFNAMSIZ},
+};
+use crate::{net::iface::iter_all_ifaces, prelude::*};
+
+/// The number of hooks.
+///
+/// This is `NF_INET_NUMHOOKS` in Linux.
+pub(super) const NUM_HOOKS: usize = 5;
+
+/// A kind of iptables tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum TableKind {
+    /// The `filter` table, which decides whether the packets are accepted or dropped.
+    Filter,
+    /// The `nat` table, which decides how the connections are translated.
+    Nat,
+}
+
+impl TableKind {
+    pub(super) fn from_name(name: &[u8]) -> Option<Self> {
+        match name {
+            b"filter" => Some(Self::Filter),
+            b"nat" => Some(Self::Nat),
+            _ => None,
+        }
+    }
+
+    /// Returns the hooks used by the table.
+    pub(super) fn valid_hooks(self) -> u32 {
+        match self {
+            // `NF_INET_LOCAL_IN`, `NF_INET_FORWARD`, and `NF_INET_LOCAL_OUT`.
+            Self::Filter => (1 << 1) | (1 << 2) | (1 << 3),
+            // `NF_INET_PRE_ROUTING`, `NF_INET_LOCAL_IN`, `NF_INET_LOCAL_OUT`, and
+            // `NF_INET_POST_ROUTING`.
+            Self::Nat => (1 << 0) | (1 << 1) | (1 << 3) | (1 << 4),
+        }
+    }
+}
+
+/// An iptables table.
+pub(super) struct IptTable {
+    kind: TableKind,
+    hook_entry: [u32; NUM_HOOKS],
+    underflow: [u32; NUM_HOOKS],
+    /// The entries in the format of user space, which are reported back to user space as is.
+    entries: Vec<u8>,
+    /// The rules parsed from the entries.
+    rules: Vec<Rule>,
+    /// The indexes of the first rules of the hooks.
+    hook_rules: [usize; NUM_HOOKS],
+    /// The indexes of the rules that hold the policies of the hooks.
+    underflow_rules: [usize; NUM_HOOKS],
+}
+
+impl IptTable {
+    /// Creates a table that accepts all packets.
+    pub(super) fn new(kind: TableKind) -> Self {
+        // Each hook has a policy entry that accepts all packets. The table ends with an error
+        // entry, as Linux does.
+        // Reference: <https://elixir.bootlin.com/linux/v6.13/source/net/netfilter/xt_repldata.h>.
+        let mut entries = Vec::new();
+        let mut hook_entry = [0; NUM_HOOKS];
+        for (hook, offset) in hook_entry.iter_mut().enumerate() {
+            if kind.valid_hooks() & (1 << hook) == 0 {
+                continue;
+            }
+            *offset = entries.len() as u32;
+            append_standard_entry(&mut entries, -(NF_ACCEPT + 1));
+        }
+        let num_entries = kind.valid_hooks().count_ones() + 1;
+        append_error_entry(&mut entries);
+
+        Self::parse(kind, hook_entry, hook_entry, num_entries, entries).unwrap()
+    }
+
+    /// Parses a table from the entries in the format of user space.
+    pub(super) fn parse(
+        kind: TableKind,
+        hook_entry: [u32; NUM_HOOKS],
+        underflow: [u32; NUM_HOOKS],
+        num_entries: u32,
+        entries: Vec<u8>,
+    ) -> Result<Self> {
+        let offsets = split_entries(&entries)?;
+        if offsets.len() != num_entries as usize {
+            return_errno_with_message!(Errno::EINVAL, "the number of entries does not match");
+        }
+
+        let find_rule = |offset: u32| {
+            offsets
+                .binary_search(&(offset as usize))
+                .map_err(|_| Error::with_message(Errno::EINVAL, "the entry offset is invalid"))
+        };
+
+        let rules = offsets
+            .iter()
+            .zip(offsets.iter().skip(1).chain([&entries.len()]))
+            .map(|(start, end)| Rule::parse(&entries[*start..*end], kind, &find_rule))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut hook_rules = [0; NUM_HOOKS];
+        let mut underflow_rules = [0; NUM_HOOKS];
+        for hook in 0..NUM_HOOKS {
+            if kind.valid_hooks() & (1 << hook) == 0 {
+                continue;
+            }
+            hook_rules[hook] = find_rule(hook_entry[hook])?;
+            underflow_rules[hook] = find_rule(underflow[hook])?;
+
+            // Like Linux, the policies must be unconditional and must either accept or drop
+            // packets.
+            let policy = &rules[underflow_rules[hook]];
+            if !policy.is_unconditional() || !matches!(policy.target, Target::Verdict(_)) {
+                return_errno_with_message!(Errno::EINVAL, "the policy of the hook is invalid");
+            }
+        }
+
+        let table = Self {
+            kind,
+            hook_entry,
+            underflow,
+            entries,
+            rules,
+            hook_rules,
+            underflow_rules,
+        };
+        table.check_loops()?;
+        if kind == TableKind::Nat {
+            table.check_nat_hooks()?;
+        }
+
+        Ok(table)
+    }
+
+    pub(super) fn valid_hooks(&self) -> u32 {
+        self.kind.valid_hooks()
+    }
+
+    pub(super) fn hook_entry(&self) -> [u32; NUM_HOOKS] {
+        self.hook_entry
+    }
+
+    pub(super) fn underflow(&self) -> [u32; NUM_HOOKS] {
+        self.underflow
+    }
+
+    pub(super) fn num_entries(&self) -> u32 {
+        self.rules.len() as u32
+    }
+
+    pub(super) fn entries(&self) -> &[u8] {
+        &self.entries
+    }
+
+    /// Decides what to do with the packet at the hook.
+    pub(super) fn evaluate(&self, hook: Hook, packet: &PacketInfo) -> Decision {
+        let hook = hook_index(hook);
+        if self.valid_hooks() & (1 << hook) == 0 {
+            return Decision::Verdict(Verdict::Accept);
+        }
+
+        // IPv6 packets are handled by ip6tables, which is not supported yet.
+        let (IpAddress::Ipv4(src_addr), IpAddress::Ipv4(dst_addr)) =
+            (packet.src_addr, packet.dst_addr)
+        else {
+            return Decision::Verdict(Verdict::Accept);
+        };
+        let packet = Ipv4PacketInfo {
+            hook,
+            src_addr,
+            dst_addr,
+            info: packet,
+        };
+
+        let mut index = self.hook_rules[hook];
+        let mut return_stack = Vec::new();
+        loop {
+            // The last entry is an error entry, so this should not happen unless user space
+            // provides a malformed table.
+            let Some(rule) = self.rules.get(index) else {
+                return Decision::Verdict(Verdict::Drop);
+            };
+
+            if !rule.matches(&packet) {
+                index += 1;
+                continue;
+            }
+
+            match rule.target {
+                Target::Verdict(verdict) => return Decision::Verdict(verdict),
+                Target::Nat(nat_target) => return Decision::Nat(nat_target),
+                Target::Jump(target) => {
+                    return_stack.push(index + 1);
+                    index = target;
+                }
+                Target::Goto(target) => index = target,
+                Target::Return => {
+                    index = return_stack.pop().unwrap_or(self.underflow_rules[hook]);
+                }
+                Target::Error => return Decision::Verdict(Verdict::Drop),
+            }
+        }
+    }
+
+    /// Checks that no chains can be reached from themselves.
+    ///
+    /// This ensures that [`Self::evaluate`] always terminates.
+    fn check_loops(&self) -> Result<()> {
+        #[derive(Clone, Copy, PartialEq, Eq)]
+        enum State {
+            Unvisited,
+            Visiting,
+            Visited,
+        }
+
+        // The chains loop if and only if the graph formed by the rules has a cycle. See
+        // `Self::successors` for details about the graph.
+        let mut states = vec![State::Unvisited; self.rules.len()];
+        for hook in 0..NUM_HOOKS {
+            if self.valid_hooks() & (1 << hook) == 0 {
+                continue;
+            }
+
+            let start = self.hook_rules[hook];
+            if states[start] != State::Unvisited {
+                continue;
+            }
+
+            // Perform a depth-first search without recursion, since the rules can be many.
+            states[start] = State::Visiting;
+            let mut stack = vec![(start, self.successors(start))];
+            while let Some((index, iter)) = stack.last_mut() {
+                let Some(next) = iter.next() else {
+                    states[*index] = State::Visited;
+                    stack.pop();
+                    continue;
+                };
+
+                match states[next] {
+                    State::Unvisited => {
+                        states[next] = State::Visiting;
+                        stack.push((next, self.successors(next)));
+                    }
+                    State::Visiting => {
+                        return_errno_with_message!(Errno::ELOOP, "the chains contain a loop");
+                    }
+                    State::Visited => (),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the NAT targets are only reachable from the hooks where they can be used.
+    fn check_nat_hooks(&self) -> Result<()> {
+        for hook in 0..NUM_HOOKS {
+            if self.valid_hooks() & (1 << hook) == 0 {
+                continue;
+            }
+
+            let mut visited = vec![false; self.rules.len()];
+            let mut stack = vec![self.hook_rules[hook]];
+            while let Some(index) = stack.pop() {
+                if visited[index] {
+                    continue;
+                }
+                visited[index] = true;
+
+                if let Target::Nat(nat_target) = &self.rules[index].target
+                    && nat_target.valid_hooks() & (1 << hook) == 0
+                {
+                    return_errno_with_message!(
+                        Errno::EINVAL,
+                        "the NAT target cannot be used in the hook"
+                    );
+                }
+
+                stack.extend(self.successors(index));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the indexes of the rules that may be visited after the rule.
+    ///
+    /// The rules form a graph, where each rule has an edge to the next rule unless the rule
+    /// always stops the traversal, and has an edge to the target rule if the rule jumps.
+    fn successors(&self, index: usize) -> impl Iterator<Item = usize> {
+        let rule = &self.rules[index];
+        let (next, target) = match rule.target {
+            Target::Jump(target) => (Some(index + 1), Some(target)),
+            Target::Goto(target) if rule.is_unconditional() => (None, Some(target)),
+            Target::Goto(target) => (Some(index + 1), Some(target)),
+            Target::Verdict(_) | Target::Nat(_) | Target::Return | Target::Error
+                if rule.is_unconditional() =>
+            {
+                (None, None)
+            }
+            Target::Verdict(_) | Target::Nat(_) | Target::Return | Target::Error => {
+                (Some(index + 1), None)
+            }
+        };
+        next.filter(|next| *next < self.rules.len())
+            .into_iter()
+            .chain(target)
+    }
+}
+
+/// The decision of a table about a packet.
+pub(super) enum Decision {
+    /// Decides the verdict of the packet.
+    Verdict(Verdict),
+    /// Translates the connection of the packet.
+    Nat(NatTarget),
+}
+
+/// Splits the entries and returns the offsets of them.
+fn split_entries(entries: &[u8]) -> Result<Vec<usize>> {
+    let mut offsets = Vec::new();
+
+    let mut offset = 0;
+    while offset < entries.len() {
+        if offset % align_of::<CIptEntry>() != 0 || entries.len() - offset < size_of::<CIptEntry>()
+        {
+            return_errno_with_message!(Errno::EINVAL, "the entry is misaligned or truncated");
+        }
+
+        let entry = CIptEntry::from_first_bytes(&entries[offset..]);
+        let next_offset = entry.next_offset as usize;
+        if next_offset < size_of::<CIptEntry>() + size_of::<CXtEntryHeader>()
+            || next_offset > entries.len() - offset
+        {
+            return_errno_with_message!(Errno::EINVAL, "the entry size is invalid");
+        }
+
+        offsets.push(offset);
+        offset += next_offset;
+    }
+
+    Ok(offsets)
+}
+
+fn hook_index(hook: Hook) -> usize {
+    match hook {
+        Hook::PreRouting => 0,
+        Hook::Input => 1,
+        Hook::Forward => 2,
+        Hook::Output => 3,
+        Hook::PostRouting => 4,
+    }
+}
+
+/// A rule of iptables.
+struct Rule {
+    ip: CIptIp,
+    port_matches: Vec<PortMatch>,
+    target: Target,
+}
+
+/// A target of a rule.
+#[derive(Clone, Copy)]
+enum Target {
+    /// Decides the verdict of the packet.
+    Verdict(Verdict),
+    /// Translates the connection of the packet.
+    Nat(NatTarget),
+    /// Jumps to the rule with the index, and returns to the next rule later.
+    Jump(usize),
+    /// Goes to the rule with the index without returning.
+    Goto(usize),
+    /// Returns to the calling chain.
+    Return,
+    /// Marks the start of a user-defined chain or the end of the table.
+    Error,
+}
+
+/// A match of a TCP or UDP rule on the ports.
+struct PortMatch {
+    protocol: IpProtocol,
+    src_ports: [u16; 2],
+    dst_ports: [u16; 2],
+    inverts_src: bool,
+    inverts_dst: bool,
+}
+
+impl Rule {
+    /// Parses a rule from an entry.
+    fn parse(
+        entry: &[u8],
+        table_kind: TableKind,
+        find_rule: &impl Fn(u32) -> Result<usize>,
+    ) -> Result<Self> {
+        let header = CIptEntry::from_first_bytes(entry);
+        let target_offset = header.target_offset as usize;
+        if target_offset < size_of::<CIptEntry>()
+            || target_offset > entry.len() - size_of::<CXtEntryHeader>()
+        {
+            return_errno_with_message!(Errno::EINVAL, "the target offset is invalid");
+        }
+
+        let mut port_matches = Vec::new();
+        let mut offset = size_of::<CIptEntry>();
+        while offset < target_offset {
+            let (name, data) = parse_extension(&entry[offset..target_offset])?;
+            port_matches.push(PortMatch::parse(&header.ip, name, data)?);
+            offset += size_of::<CXtEntryHeader>() + data.len();
+        }
+
+        let (name, data) = parse_extension(&entry[target_offset..])?;
+        let target = match name {
+            XT_STANDARD_TARGET if data.len() >= size_of::<i32>() => {
+                match i32::from_ne_bytes(data[..size_of::<i32>()].try_into().unwrap()) {
+                    verdict if verdict >= 0 => {
+                        let target = find_rule(verdict as u32)?;
+                        if header.ip.flags & IPT_F_GOTO != 0 {
+                            Target::Goto(target)
+                        } else {
+                            Target::Jump(target)
+                        }
+                    }
+                    verdict if verdict == -(NF_ACCEPT + 1) => Target::Verdict(Verdict::Accept),
+                    verdict if verdict == -(NF_DROP + 1) => Target::Verdict(Verdict::Drop),
+                    XT_RETURN => Target::Return,
+                    _ => return_errno_with_message!(Errno::EINVAL, "the verdict is invalid"),
+                }
+            }
+            XT_ERROR_TARGET => Target::Error,
+            XT_STANDARD_TARGET => {
+                return_errno_with_message!(Errno::EINVAL, "the standard target is truncated");
+            }
+            _ => match NatTarget::parse(name, data)? {
+                Some(nat_target) if table_kind == TableKind::Nat => Target::Nat(nat_target),
+                Some(_) => {
+                    return_errno_with_message!(
+                        Errno::EINVAL,
+                        "the NAT target can only be used in the nat table"
+                    );
+                }
+                // TODO: Support other targets (e.g., `REJECT` and `LOG`).
+                None => return_errno_with_message!(Errno::ENOENT, "the target is not supported"),
+            },
+        };
+
+        Ok(Self {
+            ip: header.ip,
+            port_matches,
+            target,
+        })
+    }
+
+    /// Returns whether the rule matches all packets.
+    fn is_unconditional(&self) -> bool {
+        self.ip == CIptIp::new_zeroed() && self.port_matches.is_empty()
+    }
+
+    fn matches(&self, packet: &Ipv4PacketInfo) -> bool {
+        // Reference: <https://elixir.bootlin.com/linux/v6.13/source/net/ipv4/netfilter/ip_tables.c#L49>.
+        let ip = &self.ip;
+        let inverts = |flag: u8| ip.invflags & flag != 0;
+        let masked_eq = |addr: Ipv4Address, mask: [u8; 4], expected: [u8; 4]| {
+            (addr.to_bits() & u32::from_be_bytes(mask)) == u32::from_be_bytes(expected)
+        };
+
+        if masked_eq(packet.src_addr, ip.smsk, ip.src) == inverts(IPT_INV_SRCIP)
+            || masked_eq(packet.dst_addr, ip.dmsk, ip.dst) == inverts(IPT_INV_DSTIP)
+        {
+            return false;
+        }
+
+        // The input iface is only known for the incoming packets, and the output iface is only
+        // known for the outgoing packets.
+        let (in_ifindex, out_ifindex) = match packet.hook {
+            0 | 1 => (Some(packet.info.iface.index), None),
+            _ => (None, Some(packet.info.iface.index)),
+        };
+        if iface_matches(in_ifindex, &ip.iniface, &ip.iniface_mask) == inverts(IPT_INV_VIA_IN)
+            || iface_matches(out_ifindex, &ip.outiface, &ip.outiface_mask)
+                == inverts(IPT_INV_VIA_OUT)
+        {
+            return false;
+        }
+
+        let protocol: u8 = packet.info.protocol.into();
+        if ip.proto != 0 && (protocol as u16 == ip.proto) == inverts(IPT_INV_PROTO) {
+            return false;
+        }
+
+        // Packets are always reassembled, so the rules that only match the non-first fragments
+        // never match.
+        if (ip.flags & IPT_F_FRAG != 0) != inverts(IPT_INV_FRAG) {
+            return false;
+        }
+
+        self.port_matches
+            .iter()
+            .all(|port_match| port_match.matches(packet.info))
+    }
+}
+
+impl PortMatch {
+    fn parse(ip: &CIptIp, name: &[u8], data: &[u8]) -> Result<Self> {
+        let (protocol, src_ports, dst_ports, invflags, has_extra) = match name {
+            b"tcp" if data.len() >= size_of::<CXtTcp>() => {
+                let tcp = CXtTcp::from_first_bytes(data);
+                let has_extra = tcp.option != 0 || tcp.flg_mask != 0;
+                (IpProtocol::Tcp, tcp.spts, tcp.dpts, tcp.invflags, has_extra)
+            }
+            b"udp" if data.len() >= size_of::<CXtUdp>() => {
+                let udp = CXtUdp::from_first_bytes(data);
+                (IpProtocol::Udp, udp.spts, udp.dpts, udp.invflags, false)
+            }
+            b"tcp" | b"udp" => {
+                return_errno_with_message!(Errno::EINVAL, "the match is truncated");
+            }
+            // TODO: Support other matches (e.g., `icmp` and `conntrack`).
+            _ => return_errno_with_message!(Errno::ENOENT, "the match is not supported"),
+        };
+
+        // Like Linux, the protocol of the rule must be the protocol of the match.
+        let expected_protocol: u8 = protocol.into();
+        if ip.proto != expected_protocol as u16 || ip.invflags & IPT_INV_PROTO != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the protocol does not match");
+        }
+        // TODO: Support matching TCP flags and TCP options.
+        if has_extra {
+            return_errno_with_message!(
+                Errno::EOPNOTSUPP,
+                "matching TCP flags or TCP options is not supported"
+            );
+        }
+
+        Ok(Self {
+            protocol,
+            src_ports,
+            dst_ports,
+            inverts_src: invflags & XT_INV_SRCPT != 0,
+            inverts_dst: invflags & XT_INV_DSTPT != 0,
+        })
+    }
+
+    fn matches(&self, packet: &PacketInfo) -> bool {
+        // Truncated packets never match, as Linux drops them.
+        let Some((src_port, dst_port)) = packet.ports else {
+            return false;
+        };
+        let in_range = |port: u16, range: [u16; 2]| (range[0]..=range[1]).contains(&port);
+
+        packet.protocol == self.protocol
+            && in_range(src_port, self.src_ports) != self.inverts_src
+            && in_range(dst_port, self.dst_ports) != self.inverts_dst
+    }
+}
+
+/// The information of an IPv4 packet at a hook.
+struct Ipv4PacketInfo<'a> {
+    hook: usize,
+    src_addr: Ipv4Address,
+    dst_addr: Ipv4Address,
+    info: &'a PacketInfo,
+}
+
+/// Returns whether the name of the iface matches the name in the rule.
+///
+/// If the iface is not known, its name is considered to be empty.
+fn iface_matches(ifindex: Option<u32>, name: &[u8; IFNAMSIZ], mask: &[u8; IFNAMSIZ]) -> bool {
+    if mask.iter().all(|byte| *byte == 0) {
+        return true;
+    }
+
+    let mut iface_name = [0u8; IFNAMSIZ];
+    let iface =
+        ifindex.and_then(|ifindex| iter_all_ifaces().find(|iface| iface.index() == ifindex));
+    if let Some(iface) = iface {
+        let bytes = iface.name().as_bytes();
+        let len = bytes.len().min(IFNAMSIZ - 1);
+        iface_name[..len].copy_from_slice(&bytes[..len]);
+    }
+
+    iface_name
+        .iter()
+        .zip(name)
+        .zip(mask)
+        .all(|((byte, expected), mask)| (byte ^ expected) & mask == 0)
+}
+
+/// Parses a match or a target and returns its name and its data.
+fn parse_extension(bytes: &[u8]) -> Result<(&[u8], &[u8])> {
+    if bytes.len() < size_of::<CXtEntryHeader>() {
+        return_errno_with_message!(Errno::EINVAL, "the extension is truncated");
+    }
+
+    let header = CXtEntryHeader::from_first_bytes(bytes);
+    let size = header.size as usize;
+    if size < size_of::<CXtEntryHeader>() || size > bytes.len() {
+        return_errno_with_message!(Errno::EINVAL, "the extension size is invalid");
+    }
+
+    let name_len = header
+        .name
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(header.name.len());
+    let name_offset = core::mem::offset_of!(CXtEntryHeader, name);
+    let name = &bytes[name_offset..name_offset + name_len];
+
+    Ok((name, &bytes[size_of::<CXtEntryHeader>()..size]))
+}
+
+/// Appends an unconditional entry with the standard target.
+fn append_standard_entry(entries: &mut Vec<u8>, verdict: i32) {
+    let target_size = (size_of::<CXtEntryHeader>() + size_of::<i32>()).next_multiple_of(8);
+    append_entry(entries, XT_STANDARD_TARGET, &verdict.to_ne_bytes(), target_size);
+}
+
+/// Appends an unconditional entry with the error target.
+fn append_error_entry(entries: &mut Vec<u8>) {
+    let mut error_name = [0u8; XT_FUNCTION_MAXNAMELEN];
+    error_name[..XT_ERROR_TARGET.len()].copy_from_slice(XT_ERROR_TARGET);
+    let target_size = size_of::<CXtEntryHeader>() + XT_FUNCTION_MAXNAMELEN;
+    append_entry(entries, XT_ERROR_TARGET, &error_name, target_size);
+}
+
+fn append_entry(entries: &mut Vec<u8>, name: &[u8], data: &[u8], target_size: usize) {
+    let mut entry = CIptEntry::new_zeroed();
+    entry.target_offset = size_of::<CIptEntry>() as u16;
+    entry.next_offset = (size_of::<CIptEntry>() + target_size) as u16;
+    entries.extend_from_slice(entry.as_bytes());
+
+    let mut header = CXtEntryHeader::new_zeroed();
+    header.size = target_size as u16;
+    header.name[..name.len()].copy_from_slice(name);
+    entries.extend_from_slice(header.as_bytes());
+
+    let data_start = entries.len();
+    entries.extend_from_slice(data);
+    entries.resize(data_start + target_size - size_of::<CXtEntryHeader>(), 0);
+}
+
+// Netfilter verdicts.
+// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/netfilter.h#L11>.
+const NF_DROP: i32 = 0;
+const NF_ACCEPT: i32 = 1;
+const NF_REPEAT: i32 = 4;
+const XT_RETURN: i32 = -NF_REPEAT - 1;
+
+// Names of the built-in targets.
+// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/netfilter/x_tables.h#L63>.
+const XT_STANDARD_TARGET: &[u8] = b"";
+const XT_ERROR_TARGET: &[u8] = b"ERROR";
+const XT_FUNCTION_MAXNAMELEN: usize = 30;
+
+// Flags and inversion flags in `struct ipt_ip`.
+// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/netfilter_ipv4/ip_tables.h#L85>.
+const IPT_F_FRAG: u8 = 0x01;
+const IPT_F_GOTO: u8 = 0x02;
+const IPT_INV_VIA_IN: u8 = 0x01;
+const IPT_INV_VIA_OUT: u8 = 0x02;
+const IPT_INV_SRCIP: u8 = 0x08;
+const IPT_INV_DSTIP: u8 = 0x10;
+const IPT_INV_FRAG: u8 = 0x20;
+const IPT_INV_PROTO: u8 = 0x40;
+
+// Inversion flags in `struct xt_tcp` and `struct xt_udp`.
+// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/netfilter/xt_tcpudp.h#L16>.
+const XT_INV_SRCPT: u8 = 0x01;
+const XT_INV_DSTPT: u8 = 0x02;
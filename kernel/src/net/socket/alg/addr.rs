@@ -0,0 +1,60 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{net::socket::util::SocketAddr, prelude::*};
+
+/// The socket address of an algorithm socket.
+///
+/// This corresponds to `struct sockaddr_alg` in Linux.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlgSocketAddr {
+    /// The type of the algorithm (e.g., `hash`), which is a null-terminated string.
+    pub type_: [u8; 14],
+    /// The required features of the algorithm.
+    pub feat: u32,
+    /// The mask of the features.
+    pub mask: u32,
+    /// The name of the algorithm (e.g., `sha256`), which is a null-terminated string.
+    pub name: [u8; 64],
+}
+
+impl AlgSocketAddr {
+    /// Returns the type of the algorithm, or `None` if it is not a valid string.
+    pub(super) fn type_str(&self) -> Option<&str> {
+        bytes_to_str(&self.type_)
+    }
+
+    /// Returns the name of the algorithm, or `None` if it is not a valid string.
+    pub(super) fn name_str(&self) -> Option<&str> {
+        bytes_to_str(&self.name)
+    }
+}
+
+fn bytes_to_str(bytes: &[u8]) -> Option<&str> {
+    // Like Linux, the last byte is ignored so that the string is always terminated.
+    let bytes = &bytes[..bytes.len() - 1];
+    let len = bytes
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(bytes.len());
+    core::str::from_utf8(&bytes[..len]).ok()
+}
+
+impl TryFrom<SocketAddr> for AlgSocketAddr {
+    type Error = Error;
+
+    fn try_from(value: SocketAddr) -> Result<Self> {
+        match value {
+            SocketAddr::Alg(addr) => Ok(addr),
+            _ => return_errno_with_message!(
+                Errno::EINVAL,
+                "the address is in an unsupported address family"
+            ),
+        }
+    }
+}
+
+impl From<AlgSocketAddr> for SocketAddr {
+    fn from(value: AlgSocketAddr) -> Self {
+        SocketAddr::Alg(value)
+    }
+}
@@ -0,0 +1,72 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{net::socket::util::CControlHeader, prelude::*, util::net::CSocketOptionLevel};
+
+/// A control message that sets a parameter of the cryptographic operations.
+///
+/// The control messages are only sent by the user space, and never received.
+#[derive(Debug)]
+pub enum AlgControlMessage {
+    /// The IV (`ALG_SET_IV`).
+    Iv(Vec<u8>),
+    /// The direction of the operations (`ALG_SET_OP`).
+    Op(AlgOp),
+    /// The length of the associated data of AEAD operations (`ALG_SET_AEAD_ASSOCLEN`).
+    AeadAssoclen(u32),
+}
+
+/// The direction of cryptographic operations.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+pub enum AlgOp {
+    Decrypt = 0,
+    Encrypt = 1,
+}
+
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/if_alg.h#L49>.
+const ALG_SET_IV: i32 = 2;
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/if_alg.h#L50>.
+const ALG_SET_OP: i32 = 3;
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/if_alg.h#L51>.
+const ALG_SET_AEAD_ASSOCLEN: i32 = 4;
+
+impl AlgControlMessage {
+    pub fn read_from(header: &CControlHeader, reader: &mut VmReader) -> Result<Option<Self>> {
+        debug_assert_eq!(header.level(), Some(CSocketOptionLevel::SOL_ALG));
+
+        let payload_len = header.payload_len();
+        if payload_len < size_of::<u32>() {
+            return_errno_with_message!(Errno::EINVAL, "the alg control message is too short");
+        }
+        let value = reader.read_val::<u32>()?;
+
+        let (msg, read_len) = match header.type_() {
+            ALG_SET_IV => {
+                // The IV is given as `struct af_alg_iv`, which starts with the IV length.
+                let iv_len = value as usize;
+                if payload_len - size_of::<u32>() < iv_len {
+                    return_errno_with_message!(Errno::EINVAL, "the IV length is invalid");
+                }
+                let mut iv = vec![0u8; iv_len];
+                reader.read_fallible(&mut VmWriter::from(iv.as_mut_slice()))?;
+                (Self::Iv(iv), size_of::<u32>() + iv_len)
+            }
+            ALG_SET_OP => {
+                let op = AlgOp::try_from(value)
+                    .map_err(|_| Error::with_message(Errno::EINVAL, "the operation is invalid"))?;
+                (Self::Op(op), size_of::<u32>())
+            }
+            ALG_SET_AEAD_ASSOCLEN => (Self::AeadAssoclen(value), size_of::<u32>()),
+            _ => {
+                return_errno_with_message!(Errno::EINVAL, "the alg control message is unsupported")
+            }
+        };
+        reader.skip(payload_len - read_len);
+
+        Ok(Some(msg))
+    }
+
+    pub fn write_to(&self, _writer: &mut VmWriter) -> Result<CControlHeader> {
+        return_errno_with_message!(Errno::EINVAL, "alg control messages cannot be received");
+    }
+}
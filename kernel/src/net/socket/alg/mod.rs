@@ -0,0 +1,21 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! This module defines algorithm sockets.
+//!
+//! Algorithm sockets (`AF_ALG`) expose the crypto API to the user space. A socket is bound to an
+//! algorithm by its type (e.g., `skcipher`) and its name (e.g., `cbc(aes)`), and the key is set
+//! with `ALG_SET_KEY`. Then each `accept` creates an operation socket, to which the input is sent
+//! and from which the output is received. The parameters of the operations (e.g., the IV) are
+//! given as control messages of `sendmsg`.
+//!
+//! Reference: <https://docs.kernel.org/crypto/userspace-if.html>
+
+mod addr;
+mod ctrl_msg;
+mod op;
+pub mod options;
+mod socket;
+
+pub use addr::AlgSocketAddr;
+pub use ctrl_msg::AlgControlMessage;
+pub use socket::AlgSocket;
@@ -0,0 +1,589 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use super::{
+    ctrl_msg::{AlgControlMessage, AlgOp},
+    options::{AlgSetAeadAuthsize, AlgSetKey},
+    socket::{AlgTfm, KeyedOperation},
+};
+use crate::{
+    events::IoEvents,
+    fs::{file::FileLike, pseudofs::SockFs, vfs::path::Path},
+    net::socket::{
+        Socket,
+        options::{SocketOption, macros::sock_option_ref},
+        private::SocketPrivate,
+        util::{
+            ControlMessage, MessageHeader, SendRecvFlags, SocketAddr,
+            options::{GetSocketLevelOption, SetSocketLevelOption, SocketOptionSet},
+        },
+    },
+    prelude::*,
+    process::signal::{PollHandle, Pollable, Pollee},
+    util::{
+        MultiRead, MultiWrite,
+        crypto::api::{Aead, AeadAlg, HashAlg, HashState, Rng, Skcipher, SkcipherAlg},
+    },
+};
+
+/// An operation socket of an [`AlgSocket`], which is created by `accept`.
+///
+/// The input of the cryptographic operations is sent to the socket, and the output is received
+/// from the socket.
+///
+/// [`AlgSocket`]: super::AlgSocket
+pub(super) struct AlgOpSocket {
+    tfm: Arc<AlgTfm>,
+    /// The operations, which are created on first use if the key was not set on `accept`.
+    operation: Mutex<Option<KeyedOperation>>,
+    options: RwLock<SocketOptionSet>,
+
+    is_nonblocking: AtomicBool,
+    pollee: Pollee,
+    pseudo_path: Path,
+}
+
+/// The state of the cryptographic operations of an [`AlgOpSocket`].
+pub(super) enum Operation {
+    Hash(HashOperation),
+    Skcipher(CipherOperation<SkcipherTransform>),
+    Aead(CipherOperation<AeadTransform>),
+    Rng(Box<dyn Rng>),
+}
+
+/// The state of hash operations.
+pub(super) struct HashOperation {
+    alg: HashAlg,
+    key: Option<Vec<u8>>,
+    /// The ongoing computation, which exists if the last message is sent with `MSG_MORE`.
+    state: Option<Box<dyn HashState>>,
+    /// The digest that has been computed but has not been received.
+    result: Option<Vec<u8>>,
+}
+
+/// The state of cipher operations, which consists of the parameters and the pending input.
+pub(super) struct CipherOperation<T> {
+    transform: T,
+    /// Whether the parameters have been set by a `sendmsg`.
+    is_init: bool,
+    /// Whether more input is expected, i.e., the last message is sent with `MSG_MORE`.
+    is_more: bool,
+    op: AlgOp,
+    /// The IV, which is updated after each operation so that the operations can be chained.
+    iv: Vec<u8>,
+    assoc_len: usize,
+    input: Vec<u8>,
+}
+
+pub(super) struct SkcipherTransform {
+    cipher: Arc<dyn Skcipher>,
+    block_size: usize,
+}
+
+pub(super) struct AeadTransform {
+    cipher: Arc<dyn Aead>,
+    auth_size: usize,
+}
+
+/// The parameters of cipher operations that are sent as control messages.
+struct CipherParams {
+    op: AlgOp,
+    iv: Option<Vec<u8>>,
+    assoc_len: usize,
+}
+
+/// The maximum length of the pending input of cipher operations.
+///
+/// Like Linux, this is the default send buffer size (`net.core.wmem_default`) rounded down to
+/// pages.
+const MAX_INPUT_LEN: usize = 212992 / PAGE_SIZE * PAGE_SIZE;
+
+/// The maximum length of random bytes that can be received at once.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/crypto/algif_rng.c#L58>.
+const MAX_RNG_LEN: usize = 128;
+
+impl Operation {
+    pub(super) fn new_hash(alg: HashAlg, key: Option<Vec<u8>>) -> Self {
+        Self::Hash(HashOperation {
+            alg,
+            key,
+            state: None,
+            result: None,
+        })
+    }
+
+    pub(super) fn new_skcipher(alg: SkcipherAlg, cipher: Arc<dyn Skcipher>) -> Self {
+        let transform = SkcipherTransform {
+            cipher,
+            block_size: alg.block_size(),
+        };
+        Self::Skcipher(CipherOperation::new(transform, alg.iv_size()))
+    }
+
+    pub(super) fn new_aead(alg: AeadAlg, cipher: Arc<dyn Aead>, auth_size: usize) -> Self {
+        let transform = AeadTransform { cipher, auth_size };
+        Self::Aead(CipherOperation::new(transform, alg.iv_size()))
+    }
+
+    pub(super) fn new_rng(rng: Box<dyn Rng>) -> Self {
+        Self::Rng(rng)
+    }
+
+    /// Starts sending a message, which sets the parameters if there are any.
+    fn start_send(&mut self, params: Option<CipherParams>) -> Result<()> {
+        match self {
+            Self::Hash(hash) => hash.start_send(),
+            Self::Skcipher(skcipher) => skcipher.start_send(params),
+            Self::Aead(aead) => aead.start_send(params),
+            Self::Rng(_) => {
+                return_errno_with_message!(Errno::EOPNOTSUPP, "data cannot be sent to RNGs")
+            }
+        }
+    }
+
+    /// Sends some input, which fails with `EAGAIN` if no input can be accepted for now.
+    fn send(&mut self, reader: &mut dyn MultiRead) -> Result<usize> {
+        match self {
+            Self::Hash(hash) => hash.send(reader),
+            Self::Skcipher(skcipher) => skcipher.send(reader),
+            Self::Aead(aead) => aead.send(reader),
+            Self::Rng(_) => {
+                return_errno_with_message!(Errno::EOPNOTSUPP, "data cannot be sent to RNGs")
+            }
+        }
+    }
+
+    /// Finishes sending a message.
+    fn finish_send(&mut self, is_more: bool) {
+        match self {
+            Self::Hash(hash) => hash.finish_send(is_more),
+            Self::Skcipher(skcipher) => skcipher.is_more = is_more,
+            Self::Aead(aead) => aead.is_more = is_more,
+            Self::Rng(_) => (),
+        }
+    }
+
+    /// Receives the output, which fails with `EAGAIN` if the output is not ready.
+    fn recv(&mut self, writer: &mut dyn MultiWrite) -> Result<usize> {
+        match self {
+            Self::Hash(hash) => hash.recv(writer),
+            Self::Skcipher(skcipher) => skcipher.recv(writer),
+            Self::Aead(aead) => aead.recv(writer),
+            Self::Rng(rng) => {
+                let mut buf = [0u8; MAX_RNG_LEN];
+                let len = writer.sum_lens().min(MAX_RNG_LEN);
+                rng.generate(&mut buf[..len]);
+                writer.write(&mut VmReader::from(&buf[..len]))
+            }
+        }
+    }
+
+    fn check_io_events(&self) -> IoEvents {
+        match self {
+            Self::Hash(_) | Self::Rng(_) => IoEvents::IN | IoEvents::OUT,
+            Self::Skcipher(skcipher) => skcipher.check_io_events(),
+            Self::Aead(aead) => aead.check_io_events(),
+        }
+    }
+}
+
+impl HashOperation {
+    fn start_send(&mut self) -> Result<()> {
+        if self.state.is_none() {
+            self.result = None;
+            self.state = Some(self.alg.new_state(self.key.as_deref())?);
+        }
+        Ok(())
+    }
+
+    fn send(&mut self, reader: &mut dyn MultiRead) -> Result<usize> {
+        // The computation may have been finished by a concurrent `sendmsg`.
+        self.start_send()?;
+        let state = self.state.as_mut().unwrap();
+
+        let mut buf = vec![0u8; reader.sum_lens().min(PAGE_SIZE)];
+        let mut sent = 0;
+        while !reader.is_empty() {
+            let len = reader.read(&mut VmWriter::from(buf.as_mut_slice()))?;
+            state.update(&buf[..len]);
+            sent += len;
+        }
+
+        Ok(sent)
+    }
+
+    fn finish_send(&mut self, is_more: bool) {
+        if !is_more {
+            self.result = self.state.take().map(|state| state.finalize());
+        }
+    }
+
+    fn recv(&mut self, writer: &mut dyn MultiWrite) -> Result<usize> {
+        let digest = if let Some(state) = self.state.take() {
+            state.finalize()
+        } else if let Some(result) = self.result.take() {
+            result
+        } else {
+            // Like Linux, the digest of the empty message is received if nothing has been sent.
+            self.alg.new_state(self.key.as_deref())?.finalize()
+        };
+
+        // Like Linux, the digest is truncated if the buffer is too small.
+        writer.write(&mut VmReader::from(digest.as_slice()))
+    }
+
+    /// Duplicates the operation, including the ongoing computation, for `accept`.
+    fn duplicate(&self) -> Self {
+        Self {
+            alg: self.alg,
+            key: self.key.clone(),
+            state: self.state.as_ref().map(|state| state.box_clone()),
+            result: None,
+        }
+    }
+}
+
+impl CipherParams {
+    /// Collects the parameters from the control messages.
+    ///
+    /// Like Linux, the parameters are reset to their default values (except for the IV) if there
+    /// are any control messages, while the control messages at other levels are ignored.
+    fn from_control_messages(control_messages: Vec<ControlMessage>) -> Option<Self> {
+        if control_messages.is_empty() {
+            return None;
+        }
+
+        let mut params = Self {
+            op: AlgOp::Decrypt,
+            iv: None,
+            assoc_len: 0,
+        };
+        for control_message in control_messages {
+            let ControlMessage::Alg(control_message) = control_message else {
+                continue;
+            };
+            match control_message {
+                AlgControlMessage::Iv(iv) => params.iv = Some(iv),
+                AlgControlMessage::Op(op) => params.op = op,
+                AlgControlMessage::AeadAssoclen(assoc_len) => params.assoc_len = assoc_len as usize,
+            }
+        }
+
+        Some(params)
+    }
+}
+
+impl<T> CipherOperation<T> {
+    fn new(transform: T, iv_size: usize) -> Self {
+        Self {
+            transform,
+            is_init: false,
+            is_more: false,
+            op: AlgOp::Decrypt,
+            iv: vec![0; iv_size],
+            assoc_len: 0,
+            input: Vec::new(),
+        }
+    }
+
+    fn start_send(&mut self, params: Option<CipherParams>) -> Result<()> {
+        if params
+            .as_ref()
+            .and_then(|params| params.iv.as_ref())
+            .is_some_and(|iv| iv.len() != self.iv.len())
+        {
+            return_errno_with_message!(Errno::EINVAL, "the IV size is invalid");
+        }
+
+        if self.is_init && !self.is_more && !self.input.is_empty() {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "the output of the previous request has not been received"
+            );
+        }
+        self.is_init = true;
+
+        if let Some(params) = params {
+            self.op = params.op;
+            if let Some(iv) = params.iv {
+                self.iv = iv;
+            }
+            self.assoc_len = params.assoc_len;
+        }
+
+        Ok(())
+    }
+
+    fn send(&mut self, reader: &mut dyn MultiRead) -> Result<usize> {
+        let old_len = self.input.len();
+        let len = reader.sum_lens().min(MAX_INPUT_LEN.saturating_sub(old_len));
+        if len == 0 {
+            return_errno_with_message!(Errno::EAGAIN, "the input buffer is full");
+        }
+
+        self.input.resize(old_len + len, 0);
+        let res = reader.read(&mut VmWriter::from(&mut self.input[old_len..]));
+        self.input.truncate(old_len + *res.as_ref().unwrap_or(&0));
+
+        res
+    }
+
+    fn check_io_events(&self) -> IoEvents {
+        let mut events = IoEvents::empty();
+
+        if !self.is_more || !self.input.is_empty() {
+            events |= IoEvents::IN;
+        }
+        if self.input.len() < MAX_INPUT_LEN {
+            events |= IoEvents::OUT;
+        }
+
+        events
+    }
+}
+
+impl CipherOperation<SkcipherTransform> {
+    fn recv(&mut self, writer: &mut dyn MultiWrite) -> Result<usize> {
+        let block_size = self.transform.block_size;
+        if !self.is_init || (self.is_more && self.input.len() < block_size) {
+            return_errno_with_message!(Errno::EAGAIN, "the input is not ready");
+        }
+
+        // Like Linux, only full blocks are processed if there may be more input.
+        let mut len = self.input.len().min(writer.sum_lens());
+        if len == 0 {
+            return Ok(0);
+        }
+        if self.is_more || len < self.input.len() {
+            if len < block_size {
+                return_errno_with_message!(Errno::EINVAL, "the buffer is smaller than a block");
+            }
+            len -= len % block_size;
+        }
+
+        let mut data: Vec<u8> = self.input.drain(..len).collect();
+        let cipher = &self.transform.cipher;
+        match self.op {
+            AlgOp::Encrypt => cipher.encrypt(&mut self.iv, &mut data)?,
+            AlgOp::Decrypt => cipher.decrypt(&mut self.iv, &mut data)?,
+        }
+
+        writer.write(&mut VmReader::from(data.as_slice()))
+    }
+}
+
+impl CipherOperation<AeadTransform> {
+    fn recv(&mut self, writer: &mut dyn MultiWrite) -> Result<usize> {
+        if !self.is_init || self.is_more {
+            return_errno_with_message!(Errno::EAGAIN, "the input is not ready");
+        }
+
+        // The input is the associated data followed by the plaintext (or the ciphertext and the
+        // tag), and the output is the associated data followed by the ciphertext and the tag (or
+        // the plaintext).
+        let auth_size = self.transform.auth_size;
+        let min_input_len = match self.op {
+            AlgOp::Encrypt => self.assoc_len,
+            AlgOp::Decrypt => self.assoc_len + auth_size,
+        };
+        if self.input.len() < min_input_len {
+            return_errno_with_message!(Errno::EINVAL, "the input is too short");
+        }
+        let output_len = match self.op {
+            AlgOp::Encrypt => self.input.len() + auth_size,
+            AlgOp::Decrypt => self.input.len() - auth_size,
+        };
+        if writer.sum_lens() < output_len {
+            // TODO: Support processing part of the input like Linux.
+            return_errno_with_message!(Errno::EINVAL, "the buffer is too small for the output");
+        }
+
+        let mut data = core::mem::take(&mut self.input);
+        let cipher = &self.transform.cipher;
+        let (assoc, payload) = data.split_at_mut(self.assoc_len);
+        match self.op {
+            AlgOp::Encrypt => {
+                let mut tag = vec![0u8; auth_size];
+                cipher.seal(&self.iv, assoc, payload, &mut tag);
+                data.extend_from_slice(&tag);
+            }
+            AlgOp::Decrypt => {
+                let (payload, tag) = payload.split_at_mut(payload.len() - auth_size);
+                cipher.open(&self.iv, assoc, payload, tag)?;
+                data.truncate(output_len);
+            }
+        }
+
+        writer.write(&mut VmReader::from(data.as_slice()))
+    }
+}
+
+impl AlgOpSocket {
+    pub(super) fn new(tfm: Arc<AlgTfm>, operation: Option<KeyedOperation>) -> Arc<Self> {
+        Arc::new(Self {
+            tfm,
+            operation: Mutex::new(operation),
+            options: RwLock::new(SocketOptionSet::default()),
+            is_nonblocking: AtomicBool::new(false),
+            pollee: Pollee::new(),
+            pseudo_path: SockFs::new_path(),
+        })
+    }
+
+    /// Calls `f` with the operations, which fails with `ENOKEY` if the key is not set.
+    fn with_operation<F, R>(&self, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut Operation) -> Result<R>,
+    {
+        let mut operation = self.operation.lock();
+        if operation.is_none() {
+            *operation = Some(self.tfm.new_operation()?);
+        }
+        f(&mut operation.as_mut().unwrap().operation)
+    }
+
+    fn try_send(&self, reader: &mut dyn MultiRead) -> Result<usize> {
+        let sent = self.with_operation(|operation| operation.send(reader))?;
+        self.pollee.notify(IoEvents::IN);
+        Ok(sent)
+    }
+
+    fn try_recv(&self, writer: &mut dyn MultiWrite) -> Result<usize> {
+        let received = self.with_operation(|operation| operation.recv(writer))?;
+        self.pollee.notify(IoEvents::OUT);
+        Ok(received)
+    }
+}
+
+impl Pollable for AlgOpSocket {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.pollee.poll_with(mask, poller, || {
+            match self.operation.lock().as_ref() {
+                Some(keyed) => keyed.operation.check_io_events(),
+                // The operations fail immediately if the key is not set.
+                None => IoEvents::IN | IoEvents::OUT,
+            }
+        })
+    }
+}
+
+impl SocketPrivate for AlgOpSocket {
+    fn is_nonblocking(&self) -> bool {
+        self.is_nonblocking.load(Ordering::Relaxed)
+    }
+
+    fn set_nonblocking(&self, is_nonblocking: bool) {
+        self.is_nonblocking.store(is_nonblocking, Ordering::Relaxed);
+    }
+}
+
+impl Socket for AlgOpSocket {
+    fn accept(&self) -> Result<(Arc<dyn FileLike>, SocketAddr)> {
+        let operation = self.with_operation(|operation| {
+            let Operation::Hash(hash) = operation else {
+                return_errno_with_message!(
+                    Errno::EOPNOTSUPP,
+                    "only the operation sockets of hash algorithms can be duplicated"
+                );
+            };
+            Ok(Operation::Hash(hash.duplicate()))
+        })?;
+
+        let operation = self.tfm.dup_operation(operation);
+        let socket = AlgOpSocket::new(self.tfm.clone(), Some(operation));
+
+        Ok((socket, self.tfm.addr().into()))
+    }
+
+    fn sendmsg(
+        &self,
+        reader: &mut dyn MultiRead,
+        message_header: MessageHeader,
+        flags: SendRecvFlags,
+    ) -> Result<usize> {
+        // TODO: Deal with other flags. Only MSG_MORE and MSG_DONTWAIT are handled here.
+        if !flags
+            .sub(SendRecvFlags::MSG_MORE | SendRecvFlags::MSG_DONTWAIT)
+            .is_all_supported()
+        {
+            warn!("unsupported flags: {:?}", flags);
+        }
+
+        let params = CipherParams::from_control_messages(message_header.control_messages);
+        self.with_operation(|operation| operation.start_send(params))?;
+
+        let mut sent = 0;
+        while !reader.is_empty() {
+            match self.block_on_with_flags(IoEvents::OUT, flags, || self.try_send(reader)) {
+                Ok(len) => sent += len,
+                // Like Linux, the message is not finished if the input is partially sent.
+                Err(_) if sent > 0 => return Ok(sent),
+                Err(err) => return Err(err),
+            }
+        }
+
+        let is_more = flags.contains(SendRecvFlags::MSG_MORE);
+        self.with_operation(|operation| {
+            operation.finish_send(is_more);
+            Ok(())
+        })?;
+        self.pollee.notify(IoEvents::IN);
+
+        Ok(sent)
+    }
+
+    fn recvmsg(
+        &self,
+        writer: &mut dyn MultiWrite,
+        flags: SendRecvFlags,
+    ) -> Result<(usize, MessageHeader)> {
+        // TODO: Deal with other flags. Only MSG_DONTWAIT is handled here.
+        if !flags.sub(SendRecvFlags::MSG_DONTWAIT).is_all_supported() {
+            warn!("unsupported flags: {:?}", flags);
+        }
+
+        let received = self.block_on_with_flags(IoEvents::IN, flags, || self.try_recv(writer))?;
+
+        Ok((received, MessageHeader::new(None, Vec::new())))
+    }
+
+    fn get_option(&self, option: &mut dyn SocketOption) -> Result<()> {
+        // Deal with socket-level options
+        self.options.read().get_option(option, self)
+    }
+
+    fn set_option(&self, option: &dyn SocketOption) -> Result<()> {
+        sock_option_ref!(match option {
+            _set_key @ AlgSetKey => {
+                return_errno_with_message!(
+                    Errno::ENOPROTOOPT,
+                    "the key cannot be set on operation sockets"
+                );
+            }
+            _set_auth_size @ AlgSetAeadAuthsize => {
+                return_errno_with_message!(
+                    Errno::ENOPROTOOPT,
+                    "the authentication tag size cannot be set on operation sockets"
+                );
+            }
+            _ => (),
+        });
+
+        // Deal with socket-level options
+        self.options.write().set_option(option, self)
+    }
+
+    fn pseudo_path(&self) -> &Path {
+        &self.pseudo_path
+    }
+}
+
+impl GetSocketLevelOption for AlgOpSocket {
+    fn is_listening(&self) -> bool {
+        false
+    }
+}
+
+impl SetSocketLevelOption for AlgOpSocket {}
@@ -0,0 +1,36 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::fmt;
+
+use crate::{net::socket::options::macros::impl_socket_options, prelude::*};
+
+impl_socket_options!(
+    pub struct AlgSetKey(AlgKey);
+    pub struct AlgSetAeadAuthsize(AeadAuthSize);
+);
+
+/// The key (or the seed of a random number generator) of `ALG_SET_KEY`.
+#[derive(Clone)]
+pub struct AlgKey(pub Vec<u8>);
+
+impl AlgKey {
+    /// The maximum length of a key.
+    ///
+    /// Like Linux, this is the default value of `net.core.optmem_max`.
+    pub const MAX_LEN: usize = 128 * 1024;
+}
+
+impl fmt::Debug for AlgKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The key is secret, so only its length is printed.
+        f.debug_struct("AlgKey")
+            .field("len", &self.0.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// The authentication tag size of `ALG_SET_AEAD_AUTHSIZE`.
+///
+/// Unlike other options, the size is passed as the option length.
+#[derive(Debug, Clone, Copy)]
+pub struct AeadAuthSize(pub u32);
@@ -0,0 +1,356 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use super::{
+    AlgSocketAddr,
+    op::{AlgOpSocket, Operation},
+    options::{AlgSetAeadAuthsize, AlgSetKey},
+};
+use crate::{
+    events::IoEvents,
+    fs::{file::FileLike, pseudofs::SockFs, vfs::path::Path},
+    net::socket::{
+        Socket,
+        options::{SocketOption, macros::sock_option_ref},
+        private::SocketPrivate,
+        util::{
+            MessageHeader, SendRecvFlags, SocketAddr,
+            options::{GetSocketLevelOption, SetSocketLevelOption, SocketOptionSet},
+        },
+    },
+    prelude::*,
+    process::signal::{PollHandle, Pollable, Pollee},
+    util::{
+        MultiRead, MultiWrite,
+        crypto::api::{Aead, AeadAlg, HashAlg, RngAlg, Skcipher, SkcipherAlg},
+    },
+};
+
+/// An algorithm socket (`AF_ALG`).
+///
+/// The socket holds the algorithm and its key, and creates an [`AlgOpSocket`] on each `accept`.
+/// The socket itself cannot send or receive data.
+pub struct AlgSocket {
+    tfm: Mutex<Option<Arc<AlgTfm>>>,
+    options: RwLock<SocketOptionSet>,
+
+    is_nonblocking: AtomicBool,
+    pollee: Pollee,
+    pseudo_path: Path,
+}
+
+/// The algorithm that an [`AlgSocket`] is bound to, which is shared with its operation sockets.
+pub(super) struct AlgTfm {
+    addr: AlgSocketAddr,
+    transform: Mutex<Transform>,
+    /// The token that is held by the operation sockets that have used the key.
+    ///
+    /// Like Linux, the key cannot be changed once such operation sockets exist. The operation
+    /// sockets that are created before the key is set can use the key after it is set.
+    keyed_token: Arc<()>,
+}
+
+/// The operations of an operation socket, which are created with the key.
+pub(super) struct KeyedOperation {
+    pub(super) operation: Operation,
+    _keyed_token: Arc<()>,
+}
+
+/// The algorithm with its key and parameters.
+enum Transform {
+    Hash {
+        alg: HashAlg,
+        key: Option<Vec<u8>>,
+    },
+    Skcipher {
+        alg: SkcipherAlg,
+        cipher: Option<Arc<dyn Skcipher>>,
+    },
+    Aead {
+        alg: AeadAlg,
+        cipher: Option<Arc<dyn Aead>>,
+        auth_size: usize,
+    },
+    Rng {
+        alg: RngAlg,
+        seed: Vec<u8>,
+    },
+}
+
+/// The only feature that can be requested in [`AlgSocketAddr`].
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/crypto/af_alg.c#L161>.
+const CRYPTO_ALG_KERN_DRIVER_ONLY: u32 = 0x1000;
+
+impl Transform {
+    fn new(addr: &AlgSocketAddr) -> Result<Self> {
+        let (Some(type_), Some(name)) = (addr.type_str(), addr.name_str()) else {
+            return_errno_with_message!(Errno::ENOENT, "the algorithm does not exist");
+        };
+
+        let transform = match type_ {
+            "hash" => Self::Hash {
+                alg: HashAlg::from_name(name)?,
+                key: None,
+            },
+            "skcipher" => Self::Skcipher {
+                alg: SkcipherAlg::from_name(name)?,
+                cipher: None,
+            },
+            "aead" => {
+                let alg = AeadAlg::from_name(name)?;
+                Self::Aead {
+                    alg,
+                    cipher: None,
+                    auth_size: alg.max_auth_size(),
+                }
+            }
+            "rng" => Self::Rng {
+                alg: RngAlg::from_name(name)?,
+                seed: Vec::new(),
+            },
+            _ => return_errno_with_message!(Errno::ENOENT, "the algorithm type does not exist"),
+        };
+        Ok(transform)
+    }
+
+    fn set_key(&mut self, key: &[u8]) -> Result<()> {
+        match self {
+            Self::Hash { alg, key: old_key } => {
+                alg.check_key(key)?;
+                *old_key = Some(key.to_vec());
+            }
+            Self::Skcipher { alg, cipher } => *cipher = Some(Arc::from(alg.new_cipher(key)?)),
+            Self::Aead { alg, cipher, .. } => *cipher = Some(Arc::from(alg.new_cipher(key)?)),
+            Self::Rng { seed, .. } => *seed = key.to_vec(),
+        }
+        Ok(())
+    }
+
+    fn set_auth_size(&mut self, new_size: usize) -> Result<()> {
+        let Self::Aead { alg, auth_size, .. } = self else {
+            return_errno_with_message!(
+                Errno::ENOPROTOOPT,
+                "the authentication tag size can only be set for AEAD algorithms"
+            );
+        };
+
+        alg.check_auth_size(new_size)?;
+        *auth_size = new_size;
+
+        Ok(())
+    }
+
+    fn new_operation(&self) -> Result<Operation> {
+        let no_key = || Error::with_message(Errno::ENOKEY, "the key has not been set");
+
+        let operation = match self {
+            Self::Hash { alg, key } => {
+                if alg.requires_key() && key.is_none() {
+                    return Err(no_key());
+                }
+                Operation::new_hash(*alg, key.clone())
+            }
+            Self::Skcipher { alg, cipher } => {
+                let cipher = cipher.clone().ok_or_else(no_key)?;
+                Operation::new_skcipher(*alg, cipher)
+            }
+            Self::Aead {
+                alg,
+                cipher,
+                auth_size,
+            } => {
+                let cipher = cipher.clone().ok_or_else(no_key)?;
+                Operation::new_aead(*alg, cipher, *auth_size)
+            }
+            Self::Rng { alg, seed } => Operation::new_rng(alg.new_rng(seed)),
+        };
+        Ok(operation)
+    }
+}
+
+impl AlgTfm {
+    pub(super) fn addr(&self) -> AlgSocketAddr {
+        self.addr
+    }
+
+    /// Creates the operations with the key, which fails with `ENOKEY` if the key is not set.
+    pub(super) fn new_operation(&self) -> Result<KeyedOperation> {
+        // The lock must be held until the token is cloned, so that the key cannot be changed.
+        let transform = self.transform.lock();
+        let operation = transform.new_operation()?;
+        Ok(KeyedOperation {
+            operation,
+            _keyed_token: self.keyed_token.clone(),
+        })
+    }
+
+    /// Duplicates the operations, which share the same key.
+    pub(super) fn dup_operation(&self, operation: Operation) -> KeyedOperation {
+        KeyedOperation {
+            operation,
+            _keyed_token: self.keyed_token.clone(),
+        }
+    }
+
+    fn set_key(&self, key: &[u8]) -> Result<()> {
+        let mut transform = self.transform.lock();
+        self.check_no_keyed_ops()?;
+        transform.set_key(key)
+    }
+
+    fn set_auth_size(&self, auth_size: usize) -> Result<()> {
+        let mut transform = self.transform.lock();
+        self.check_no_keyed_ops()?;
+        transform.set_auth_size(auth_size)
+    }
+
+    fn check_no_keyed_ops(&self) -> Result<()> {
+        if Arc::strong_count(&self.keyed_token) > 1 {
+            return_errno_with_message!(
+                Errno::EBUSY,
+                "the key cannot be changed after operation sockets use it"
+            );
+        }
+        Ok(())
+    }
+}
+
+impl AlgSocket {
+    pub fn new(is_nonblocking: bool) -> Arc<Self> {
+        Arc::new(Self {
+            tfm: Mutex::new(None),
+            options: RwLock::new(SocketOptionSet::default()),
+            is_nonblocking: AtomicBool::new(is_nonblocking),
+            pollee: Pollee::new(),
+            pseudo_path: SockFs::new_path(),
+        })
+    }
+
+    fn tfm(&self) -> Option<Arc<AlgTfm>> {
+        self.tfm.lock().clone()
+    }
+}
+
+impl Pollable for AlgSocket {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.pollee.poll_with(mask, poller, IoEvents::empty)
+    }
+}
+
+impl SocketPrivate for AlgSocket {
+    fn is_nonblocking(&self) -> bool {
+        self.is_nonblocking.load(Ordering::Relaxed)
+    }
+
+    fn set_nonblocking(&self, is_nonblocking: bool) {
+        self.is_nonblocking.store(is_nonblocking, Ordering::Relaxed);
+    }
+}
+
+impl Socket for AlgSocket {
+    fn bind(&self, socket_addr: SocketAddr) -> Result<()> {
+        let addr = AlgSocketAddr::try_from(socket_addr)?;
+
+        if (addr.feat | addr.mask) & !CRYPTO_ALG_KERN_DRIVER_ONLY != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the algorithm features are invalid");
+        }
+
+        let transform = Transform::new(&addr)?;
+
+        let mut tfm = self.tfm.lock();
+        // The operation sockets hold references to the algorithm.
+        if tfm.as_ref().is_some_and(|tfm| Arc::strong_count(tfm) > 1) {
+            return_errno_with_message!(
+                Errno::EBUSY,
+                "the socket cannot be rebound after operation sockets are created"
+            );
+        }
+        *tfm = Some(Arc::new(AlgTfm {
+            addr,
+            transform: Mutex::new(transform),
+            keyed_token: Arc::new(()),
+        }));
+
+        Ok(())
+    }
+
+    fn accept(&self) -> Result<(Arc<dyn FileLike>, SocketAddr)> {
+        let Some(tfm) = self.tfm() else {
+            return_errno_with_message!(Errno::EINVAL, "the socket is not bound");
+        };
+
+        // Like Linux, the operation socket can be created before the key is set.
+        let operation = match tfm.new_operation() {
+            Ok(operation) => Some(operation),
+            Err(err) if err.error() == Errno::ENOKEY => None,
+            Err(err) => return Err(err),
+        };
+        let addr = tfm.addr();
+        let socket = AlgOpSocket::new(tfm, operation);
+
+        Ok((socket, addr.into()))
+    }
+
+    fn sendmsg(
+        &self,
+        _reader: &mut dyn MultiRead,
+        _message_header: MessageHeader,
+        _flags: SendRecvFlags,
+    ) -> Result<usize> {
+        return_errno_with_message!(
+            Errno::EOPNOTSUPP,
+            "data can only be sent to operation sockets"
+        );
+    }
+
+    fn recvmsg(
+        &self,
+        _writer: &mut dyn MultiWrite,
+        _flags: SendRecvFlags,
+    ) -> Result<(usize, MessageHeader)> {
+        return_errno_with_message!(
+            Errno::EOPNOTSUPP,
+            "data can only be received from operation sockets"
+        );
+    }
+
+    fn get_option(&self, option: &mut dyn SocketOption) -> Result<()> {
+        // Deal with socket-level options
+        self.options.read().get_option(option, self)
+    }
+
+    fn set_option(&self, option: &dyn SocketOption) -> Result<()> {
+        sock_option_ref!(match option {
+            set_key @ AlgSetKey => {
+                let Some(tfm) = self.tfm() else {
+                    return_errno_with_message!(Errno::ENOPROTOOPT, "the socket is not bound");
+                };
+                return tfm.set_key(&set_key.get().unwrap().0);
+            }
+            set_auth_size @ AlgSetAeadAuthsize => {
+                let Some(tfm) = self.tfm() else {
+                    return_errno_with_message!(Errno::ENOPROTOOPT, "the socket is not bound");
+                };
+                return tfm.set_auth_size(set_auth_size.get().unwrap().0 as usize);
+            }
+            _ => (),
+        });
+
+        // Deal with socket-level options
+        self.options.write().set_option(option, self)
+    }
+
+    fn pseudo_path(&self) -> &Path {
+        &self.pseudo_path
+    }
+}
+
+impl GetSocketLevelOption for AlgSocket {
+    fn is_listening(&self) -> bool {
+        false
+    }
+}
+
+impl SetSocketLevelOption for AlgSocket {}
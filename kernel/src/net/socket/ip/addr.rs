@@ -1,21 +1,77 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use aster_bigtcp::wire::{IpAddress, IpEndpoint, Ipv4Address};
+use aster_bigtcp::wire::{IpAddress, IpEndpoint, Ipv4Address, Ipv6Address};
 
 use crate::{net::socket::util::SocketAddr, prelude::*, return_errno_with_message};
 
-impl TryFrom<SocketAddr> for IpEndpoint {
-    type Error = Error;
+/// The address family of an IP socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpFamily {
+    /// The `AF_INET` family, which uses IPv4 addresses.
+    Ipv4,
+    /// The `AF_INET6` family, which uses IPv6 addresses.
+    ///
+    /// Unless `IPV6_V6ONLY` is set, the sockets can also communicate with IPv4 peers using the
+    /// IPv4-mapped IPv6 addresses (e.g., `::ffff:127.0.0.1`).
+    Ipv6,
+}
 
-    fn try_from(value: SocketAddr) -> Result<Self> {
-        match value {
-            SocketAddr::IPv4(addr, port) => Ok(IpEndpoint::new(addr.into(), port)),
+impl IpFamily {
+    /// Converts a socket address to an IP endpoint.
+    ///
+    /// For IPv6 sockets, an IPv4-mapped IPv6 address is converted to an IPv4 endpoint, unless
+    /// `is_v6only` is true, in which case the address is rejected.
+    pub(super) fn endpoint_from(
+        self,
+        socket_addr: SocketAddr,
+        is_v6only: bool,
+    ) -> Result<IpEndpoint> {
+        match (self, socket_addr) {
+            (IpFamily::Ipv4, SocketAddr::IPv4(addr, port)) => {
+                Ok(IpEndpoint::new(addr.into(), port))
+            }
+            (IpFamily::Ipv6, SocketAddr::IPv6(addr, port)) => {
+                let Some(ipv4_addr) = addr.to_ipv4_mapped() else {
+                    return Ok(IpEndpoint::new(addr.into(), port));
+                };
+                if is_v6only {
+                    return_errno_with_message!(
+                        Errno::EINVAL,
+                        "IPv4-mapped addresses cannot be used by IPv6-only sockets"
+                    );
+                }
+                Ok(IpEndpoint::new(ipv4_addr.into(), port))
+            }
             _ => return_errno_with_message!(
                 Errno::EAFNOSUPPORT,
                 "the address is in an unsupported address family"
             ),
         }
     }
+
+    /// Converts an IP endpoint to a socket address.
+    ///
+    /// For IPv6 sockets, an IPv4 endpoint is converted to an IPv4-mapped IPv6 address.
+    pub(super) fn socket_addr_from(self, endpoint: IpEndpoint) -> SocketAddr {
+        match (self, endpoint.addr) {
+            (IpFamily::Ipv6, IpAddress::Ipv4(ipv4_addr)) => {
+                SocketAddr::IPv6(ipv4_addr.to_ipv6_mapped(), endpoint.port)
+            }
+            _ => endpoint.into(),
+        }
+    }
+
+    /// Returns a local endpoint, which indicates that the local endpoint is unspecified.
+    ///
+    /// According to the Linux man pages and the Linux implementation, `getsockname()` will _not_
+    /// fail even if the socket is unbound. Instead, it will return an unspecified socket address.
+    /// This unspecified endpoint helps with that.
+    pub(super) const fn unspecified_local_endpoint(self) -> IpEndpoint {
+        match self {
+            IpFamily::Ipv4 => IpEndpoint::new(IpAddress::Ipv4(Ipv4Address::UNSPECIFIED), 0),
+            IpFamily::Ipv6 => IpEndpoint::new(IpAddress::Ipv6(Ipv6Address::UNSPECIFIED), 0),
+        }
+    }
 }
 
 impl From<IpEndpoint> for SocketAddr {
@@ -23,15 +79,7 @@ impl From<IpEndpoint> for SocketAddr {
         let port = endpoint.port;
         match endpoint.addr {
             IpAddress::Ipv4(addr) => SocketAddr::IPv4(addr, port),
-            // TODO: support IPv6
+            IpAddress::Ipv6(addr) => SocketAddr::IPv6(addr, port),
         }
     }
 }
-
-/// A local endpoint, which indicates that the local endpoint is unspecified.
-///
-/// According to the Linux man pages and the Linux implementation, `getsockname()` will _not_ fail
-/// even if the socket is unbound. Instead, it will return an unspecified socket address. This
-/// unspecified endpoint helps with that.
-pub(super) const UNSPECIFIED_LOCAL_ENDPOINT: IpEndpoint =
-    IpEndpoint::new(IpAddress::Ipv4(Ipv4Address::UNSPECIFIED), 0);
@@ -2,41 +2,64 @@
 
 use aster_bigtcp::{
     errors::BindError,
-    iface::BindPortConfig,
-    wire::{IpAddress, IpEndpoint},
+    iface::{BindPortConfig, PortReuse},
+    wire::{IpAddress, IpEndpoint, Ipv4Address, Ipv4Cidr, Ipv6Address},
 };
 
+use super::options::{IpMembershipRequest, IpOptionSet};
 use crate::{
-    net::iface::{BoundPort, Iface, iter_all_ifaces, loopback_iface, virtio_iface},
+    net::{
+        iface::{BoundPort, Iface, iter_all_ifaces, loopback_iface, virtio_iface},
+        socket::util::options::SocketOptionSet,
+    },
     prelude::*,
 };
 
+/// The options that decide how a socket is bound to a local endpoint.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct BindOptions {
+    /// How the port can be shared with other sockets.
+    pub(super) reuse: PortReuse,
+    /// The index of the iface that the socket is bound to by `SO_BINDTODEVICE`, or zero if there
+    /// is no such iface.
+    pub(super) bound_ifindex: u32,
+    /// Whether the socket can be bound to a nonlocal address (`IP_FREEBIND`).
+    pub(super) is_freebind: bool,
+}
+
+impl BindOptions {
+    pub(super) fn new(socket_options: &SocketOptionSet, ip_options: &IpOptionSet) -> Self {
+        Self {
+            reuse: socket_options.port_reuse(),
+            bound_ifindex: socket_options.bound_ifindex(),
+            is_freebind: ip_options.freebind(),
+        }
+    }
+}
+
 pub(super) fn get_iface_to_bind(ip_addr: &IpAddress) -> Option<Arc<Iface>> {
-    let IpAddress::Ipv4(ipv4_addr) = ip_addr;
-    iter_all_ifaces()
-        .find(|iface| {
-            if let Some(iface_ipv4_addr) = iface.ipv4_addr() {
-                iface_ipv4_addr == *ipv4_addr
-            } else {
-                false
-            }
-        })
-        .map(Clone::clone)
+    iter_all_ifaces().find(|iface| iface.has_ip_addr(*ip_addr))
 }
 
 /// Get a suitable iface to deal with sendto/connect request if the socket is not bound to an iface.
-/// If the remote address is the same as that of some iface, we will use the iface.
-/// Otherwise, we will use a default interface.
-fn get_ephemeral_iface(remote_ip_addr: &IpAddress) -> Arc<Iface> {
-    let IpAddress::Ipv4(remote_ipv4_addr) = remote_ip_addr;
-    if let Some(iface) = iter_all_ifaces().find(|iface| {
-        if let Some(iface_ipv4_addr) = iface.ipv4_addr() {
-            iface_ipv4_addr == *remote_ipv4_addr
-        } else {
-            false
-        }
-    }) {
-        return iface.clone();
+/// If the remote address is the same as that of some iface, or is in the subnet of some iface, we
+/// will use the iface. Otherwise, we will use a default interface.
+pub(super) fn get_ephemeral_iface(remote_ip_addr: &IpAddress) -> Arc<Iface> {
+    if let Some(iface) = iter_all_ifaces().find(|iface| iface.has_ip_addr(*remote_ip_addr)) {
+        return iface;
+    }
+
+    if let IpAddress::Ipv4(remote_ipv4_addr) = remote_ip_addr
+        && let Some(iface) = iter_all_ifaces().find(|iface| {
+            iface
+                .ipv4_addr()
+                .zip(iface.prefix_len())
+                .is_some_and(|(addr, prefix_len)| {
+                    Ipv4Cidr::new(addr, prefix_len).contains_addr(remote_ipv4_addr)
+                })
+        })
+    {
+        return iface;
     }
 
     // FIXME: Instead of hardcoding the rules here, we should choose the
@@ -48,20 +71,60 @@ fn get_ephemeral_iface(remote_ip_addr: &IpAddress) -> Arc<Iface> {
     }
 }
 
-pub(super) fn bind_port(endpoint: &IpEndpoint, can_reuse: bool) -> Result<BoundPort> {
-    let iface = match get_iface_to_bind(&endpoint.addr) {
-        Some(iface) => iface,
-        None => {
+/// Gets the iface with the specified index.
+pub(super) fn get_iface_by_index(index: u32) -> Result<Arc<Iface>> {
+    iter_all_ifaces()
+        .find(|iface| iface.index() == index)
+        .ok_or_else(|| Error::with_message(Errno::ENODEV, "the interface does not exist"))
+}
+
+/// Gets the iface to join or leave an IPv4 multicast group.
+///
+/// The iface can be specified by its index or its address in the request. Otherwise, the default
+/// interface is used.
+pub(super) fn get_multicast_iface(request: &IpMembershipRequest) -> Result<Arc<Iface>> {
+    if request.interface_index != 0 {
+        return get_iface_by_index(request.interface_index);
+    }
+
+    if !request.interface_addr.is_unspecified() {
+        return get_iface_to_bind(&IpAddress::Ipv4(request.interface_addr)).ok_or_else(|| {
+            Error::with_message(Errno::ENODEV, "no interface has the specified address")
+        });
+    }
+
+    Ok(get_ephemeral_iface(&IpAddress::Ipv4(request.group_addr)))
+}
+
+pub(super) fn bind_port(endpoint: &IpEndpoint, options: &BindOptions) -> Result<BoundPort> {
+    let iface = if options.bound_ifindex != 0 {
+        // FIXME: Linux allows a socket bound to an iface to use the addresses of other ifaces.
+        // However, our sockets can only receive packets from the iface to which their ports are
+        // bound, so we reject such addresses.
+        let iface = get_iface_by_index(options.bound_ifindex)?;
+        if !options.is_freebind && !iface.has_ip_addr(endpoint.addr) {
             return_errno_with_message!(
                 Errno::EADDRNOTAVAIL,
-                "the address is not available from the local machine"
+                "the address is not available from the bound interface"
             );
         }
+        iface
+    } else if let Some(iface) = get_iface_to_bind(&endpoint.addr) {
+        iface
+    } else if options.is_freebind {
+        // The address may be assigned to an iface later. Choose the iface through which the
+        // address would be reached.
+        get_ephemeral_iface(&endpoint.addr)
+    } else {
+        return_errno_with_message!(
+            Errno::EADDRNOTAVAIL,
+            "the address is not available from the local machine"
+        );
     };
 
-    let bind_port_config = BindPortConfig::new(endpoint.port, can_reuse);
+    let bind_port_config = BindPortConfig::new(endpoint.port, options.reuse);
 
-    Ok(iface.bind(bind_port_config)?)
+    Ok(iface.bind(endpoint.addr, bind_port_config)?)
 }
 
 impl From<BindError> for Error {
@@ -77,8 +140,45 @@ impl From<BindError> for Error {
     }
 }
 
-pub(super) fn get_ephemeral_endpoint(remote_endpoint: &IpEndpoint) -> IpEndpoint {
-    let iface = get_ephemeral_iface(&remote_endpoint.addr);
-    let ip_addr = iface.ipv4_addr().unwrap();
-    IpEndpoint::new(IpAddress::Ipv4(ip_addr), 0)
+/// Gets a local endpoint with an ephemeral port to communicate with the remote endpoint.
+///
+/// If the socket is bound to an iface (see [`BindOptions::bound_ifindex`]), the local address is
+/// selected from that iface.
+pub(super) fn get_ephemeral_endpoint(
+    remote_endpoint: &IpEndpoint,
+    options: &BindOptions,
+) -> Result<IpEndpoint> {
+    let iface = match options.bound_ifindex {
+        0 => get_ephemeral_iface(&remote_endpoint.addr),
+        ifindex => get_iface_by_index(ifindex)?,
+    };
+    let ip_addr = match remote_endpoint.addr {
+        IpAddress::Ipv4(_) => {
+            // The iface may have no IPv4 address if it has been removed. Binding to the
+            // unspecified address will fail with `EADDRNOTAVAIL`.
+            IpAddress::Ipv4(iface.ipv4_addr().unwrap_or(Ipv4Address::UNSPECIFIED))
+        }
+        IpAddress::Ipv6(remote_ipv6_addr) => {
+            IpAddress::Ipv6(select_ipv6_src_addr(&iface, remote_ipv6_addr))
+        }
+    };
+    Ok(IpEndpoint::new(ip_addr, 0))
+}
+
+/// Selects the source address of the iface to communicate with the remote IPv6 address.
+///
+/// This is a simplified version of the default address selection algorithm in
+/// <https://datatracker.ietf.org/doc/html/rfc6724#section-5>. Currently, only the scope of the
+/// addresses is taken into account.
+fn select_ipv6_src_addr(iface: &Iface, remote_ipv6_addr: Ipv6Address) -> Ipv6Address {
+    let is_remote_link_local = remote_ipv6_addr.is_unicast_link_local();
+    let ipv6_addrs = iface.ipv6_addrs();
+
+    ipv6_addrs
+        .iter()
+        .map(|ipv6_cidr| ipv6_cidr.address())
+        .find(|ipv6_addr| ipv6_addr.is_unicast_link_local() == is_remote_link_local)
+        .or_else(|| ipv6_addrs.first().map(|ipv6_cidr| ipv6_cidr.address()))
+        // Binding to the unspecified address will fail with `EADDRNOTAVAIL`.
+        .unwrap_or(Ipv6Address::UNSPECIFIED)
 }
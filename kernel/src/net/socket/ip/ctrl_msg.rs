@@ -0,0 +1,186 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_bigtcp::wire::Ipv4Address;
+
+use super::IpFamily;
+use crate::{
+    net::{iface::Iface, socket::util::CControlHeader},
+    prelude::*,
+    util::net::CSocketOptionLevel,
+};
+
+#[derive(Debug)]
+pub struct IpControlMessage(Message);
+
+#[derive(Debug)]
+enum Message {
+    RecvErr(RecvErrMessage),
+    Pktinfo(CInPktinfo),
+}
+
+impl IpControlMessage {
+    /// Creates a control message that notifies the completion of zero-copy transmissions.
+    ///
+    /// The IDs of the completed transmissions range from `first_id` to `last_id` (inclusive,
+    /// wrapping around on overflow).
+    pub(super) fn new_zerocopy_completion(family: IpFamily, first_id: u32, last_id: u32) -> Self {
+        let err = CSockExtendedErr {
+            ee_errno: 0,
+            ee_origin: SO_EE_ORIGIN_ZEROCOPY,
+            ee_type: 0,
+            // FIXME: The data is always copied. See `ErrorQueue::complete_zerocopy_send` for
+            // details.
+            ee_code: SO_EE_CODE_ZEROCOPY_COPIED,
+            ee_pad: 0,
+            ee_info: first_id,
+            ee_data: last_id,
+        };
+
+        Self(Message::RecvErr(RecvErrMessage { family, err }))
+    }
+
+    /// Creates a control message that accompanies the transmit timestamps.
+    ///
+    /// The message tells which event (`SCM_TSTAMP_*`) the timestamps are taken at, and which send
+    /// they belong to (with `SOF_TIMESTAMPING_OPT_ID`).
+    pub(super) fn new_tx_timestamp(family: IpFamily, type_: u32, key: u32) -> Self {
+        let err = CSockExtendedErr {
+            ee_errno: Errno::ENOMSG as u32,
+            ee_origin: SO_EE_ORIGIN_TIMESTAMPING,
+            ee_type: 0,
+            ee_code: 0,
+            ee_pad: 0,
+            ee_info: type_,
+            ee_data: key,
+        };
+
+        Self(Message::RecvErr(RecvErrMessage { family, err }))
+    }
+
+    /// Creates an `IP_PKTINFO` control message for an IPv4 packet received from the iface.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.13/source/net/ipv4/ip_sockglue.c#L1396>.
+    pub(super) fn new_pktinfo(iface: &Iface, dst_addr: Ipv4Address) -> Self {
+        // The local address is the destination address, unless the packet is sent to a broadcast
+        // or multicast address, in which case the address of the iface is used instead.
+        let is_local_dst = !dst_addr.is_broadcast()
+            && !dst_addr.is_multicast()
+            && iface.broadcast_addr() != Some(dst_addr);
+        let spec_dst = if is_local_dst {
+            dst_addr
+        } else {
+            iface.ipv4_addr().unwrap_or(Ipv4Address::UNSPECIFIED)
+        };
+
+        Self(Message::Pktinfo(CInPktinfo {
+            ipi_ifindex: iface.index() as i32,
+            ipi_spec_dst: spec_dst.octets(),
+            ipi_addr: dst_addr.octets(),
+        }))
+    }
+
+    pub fn write_to(&self, writer: &mut VmWriter) -> Result<CControlHeader> {
+        match &self.0 {
+            Message::RecvErr(msg) => msg.write_to(writer),
+            Message::Pktinfo(pktinfo) => write_pktinfo(pktinfo, writer),
+        }
+    }
+}
+
+fn write_pktinfo(pktinfo: &CInPktinfo, writer: &mut VmWriter) -> Result<CControlHeader> {
+    let payload = pktinfo.as_bytes();
+
+    let payload_len = payload
+        .len()
+        .min(CControlHeader::payload_len_from_total(writer.avail())?);
+    if payload_len != payload.len() {
+        warn!("setting MSG_CTRUNC is not supported");
+    }
+
+    let header = CControlHeader::new(CSocketOptionLevel::SOL_IP, IP_PKTINFO, payload_len);
+    writer.write_val(&header)?;
+    writer.write_fallible(&mut VmReader::from(&payload[..payload_len]))?;
+
+    Ok(header)
+}
+
+/// A message from the error queue (`IP_RECVERR` or `IPV6_RECVERR`).
+#[derive(Debug)]
+struct RecvErrMessage {
+    family: IpFamily,
+    err: CSockExtendedErr,
+}
+
+impl RecvErrMessage {
+    fn write_to(&self, writer: &mut VmWriter) -> Result<CControlHeader> {
+        // The extended error is followed by the address of the node that caused the error. There
+        // is no such node for the errors we report, so the address is left as zeros
+        // (`AF_UNSPEC`).
+        //
+        // Reference: <https://elixir.bootlin.com/linux/v6.13/source/net/ipv4/ip_sockglue.c#L538>.
+        let (level, type_, offender_len) = match self.family {
+            IpFamily::Ipv4 => (CSocketOptionLevel::SOL_IP, IP_RECVERR, SOCKADDR_IN_LEN),
+            IpFamily::Ipv6 => (CSocketOptionLevel::SOL_IPV6, IPV6_RECVERR, SOCKADDR_IN6_LEN),
+        };
+
+        let max_len = size_of::<CSockExtendedErr>() + offender_len;
+        let payload_len = max_len.min(CControlHeader::payload_len_from_total(writer.avail())?);
+        if payload_len != max_len {
+            warn!("setting MSG_CTRUNC is not supported");
+        }
+
+        let mut payload = vec![0u8; max_len];
+        payload[..size_of::<CSockExtendedErr>()].copy_from_slice(self.err.as_bytes());
+
+        let header = CControlHeader::new(level, type_, payload_len);
+        writer.write_val(&header)?;
+        writer.write_fallible(&mut VmReader::from(&payload[..payload_len]))?;
+
+        Ok(header)
+    }
+}
+
+/// `sock_extended_err` in Linux.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/errqueue.h#L9>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CSockExtendedErr {
+    ee_errno: u32,
+    ee_origin: u8,
+    ee_type: u8,
+    ee_code: u8,
+    ee_pad: u8,
+    ee_info: u32,
+    ee_data: u32,
+}
+
+/// `in_pktinfo` in Linux.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/in.h#L259>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CInPktinfo {
+    ipi_ifindex: i32,
+    ipi_spec_dst: [u8; 4],
+    ipi_addr: [u8; 4],
+}
+
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/errqueue.h#L23>.
+const SO_EE_ORIGIN_TIMESTAMPING: u8 = 4;
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/errqueue.h#L24>.
+const SO_EE_ORIGIN_ZEROCOPY: u8 = 5;
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/errqueue.h#L37>.
+const SO_EE_CODE_ZEROCOPY_COPIED: u8 = 1;
+
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/in.h#L106>.
+const IP_PKTINFO: i32 = 8;
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/in.h#L109>.
+const IP_RECVERR: i32 = 11;
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/in6.h#L192>.
+const IPV6_RECVERR: i32 = 25;
+
+/// The size of `sockaddr_in` in Linux.
+const SOCKADDR_IN_LEN: usize = 16;
+/// The size of `sockaddr_in6` in Linux.
+const SOCKADDR_IN6_LEN: usize = 28;
@@ -2,6 +2,7 @@
 
 use aster_bigtcp::{
     errors::udp::{RecvError, SendError},
+    socket::UdpRecvInfo,
     wire::IpEndpoint,
 };
 
@@ -35,6 +36,38 @@ impl BoundDatagram {
     pub(super) fn bound_port(&self) -> &BoundPort {
         self.bound_socket.bound_port()
     }
+
+    pub(super) fn set_send_options(&self, ttl: u8, multicast_ttl: u8, multicast_loop: bool) {
+        self.bound_socket.set_ttl(ttl);
+        self.bound_socket.set_multicast_ttl(multicast_ttl);
+        self.bound_socket.set_multicast_loop(multicast_loop);
+    }
+
+    /// Receives a packet, like [`datagram_common::Bound::try_recv`].
+    ///
+    /// In addition, this method returns the information about the packet (e.g., when it arrived).
+    pub(super) fn try_recv_with_info(
+        &self,
+        writer: &mut dyn MultiWrite,
+        _flags: SendRecvFlags,
+    ) -> Result<(usize, IpEndpoint, UdpRecvInfo)> {
+        let result = self.bound_socket.recv(|packet, udp_metadata, recv_info| {
+            let copied_res = writer.write(&mut VmReader::from(packet));
+            let endpoint = udp_metadata.endpoint;
+            (copied_res, endpoint, recv_info)
+        });
+
+        match result {
+            Ok((Ok(res), endpoint, recv_info)) => Ok((res, endpoint, recv_info)),
+            Ok((Err(e), _, _)) => Err(e),
+            Err(RecvError::Exhausted) => {
+                return_errno_with_message!(Errno::EAGAIN, "the receive buffer is empty")
+            }
+            Err(RecvError::Truncated) => {
+                unreachable!("`recv` should never fail with `RecvError::Truncated`")
+            }
+        }
+    }
 }
 
 impl datagram_common::Bound for BoundDatagram {
@@ -55,24 +88,10 @@ impl datagram_common::Bound for BoundDatagram {
     fn try_recv(
         &self,
         writer: &mut dyn MultiWrite,
-        _flags: SendRecvFlags,
+        flags: SendRecvFlags,
     ) -> Result<(usize, Self::Endpoint)> {
-        let result = self.bound_socket.recv(|packet, udp_metadata| {
-            let copied_res = writer.write(&mut VmReader::from(packet));
-            let endpoint = udp_metadata.endpoint;
-            (copied_res, endpoint)
-        });
-
-        match result {
-            Ok((Ok(res), endpoint)) => Ok((res, endpoint)),
-            Ok((Err(e), _)) => Err(e),
-            Err(RecvError::Exhausted) => {
-                return_errno_with_message!(Errno::EAGAIN, "the receive buffer is empty")
-            }
-            Err(RecvError::Truncated) => {
-                unreachable!("`recv` should never fail with `RecvError::Truncated`")
-            }
-        }
+        let (recv_bytes, endpoint, _) = self.try_recv_with_info(writer, flags)?;
+        Ok((recv_bytes, endpoint))
     }
 
     fn try_send(
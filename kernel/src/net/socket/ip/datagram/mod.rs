@@ -2,23 +2,32 @@
 
 use core::sync::atomic::{AtomicBool, Ordering};
 
-use aster_bigtcp::wire::IpEndpoint;
+use aster_bigtcp::{
+    socket::UdpRecvInfo,
+    wire::{IpAddress, IpEndpoint},
+};
 use bound::BoundDatagram;
-use unbound::{BindOptions, UnboundDatagram};
+use unbound::UnboundDatagram;
 
-use super::addr::UNSPECIFIED_LOCAL_ENDPOINT;
+use super::{
+    addr::IpFamily, common::BindOptions, ctrl_msg::IpControlMessage, err_queue::ErrorQueue,
+    multicast::MulticastMemberships,
+};
 use crate::{
     events::IoEvents,
     fs::{pseudofs::SockFs, vfs::path::Path},
     net::{
-        iface::is_broadcast_endpoint,
+        iface::{Iface, is_broadcast_endpoint},
         socket::{
             Socket,
-            ip::options::{IpOptionSet, SetIpLevelOption},
-            options::{Error as SocketError, SocketOption, macros::sock_option_mut},
+            ip::options::{AddMembership, DropMembership, IpOptionSet, SetIpLevelOption},
+            options::{
+                Error as SocketError, SocketOption,
+                macros::{sock_option_mut, sock_option_ref},
+            },
             private::SocketPrivate,
             util::{
-                MessageHeader, SendRecvFlags, SocketAddr,
+                ControlMessage, MessageHeader, SendRecvFlags, SocketAddr, TimestampControlMessage,
                 datagram_common::{Bound, Inner, select_remote_and_bind},
                 options::{GetSocketLevelOption, SetSocketLevelOption, SocketOptionSet},
             },
@@ -37,6 +46,8 @@ pub struct DatagramSocket {
     // Lock order: `inner` first, `options` second
     inner: RwMutex<Inner<UnboundDatagram, BoundDatagram>>,
     options: RwLock<OptionSet>,
+    memberships: Mutex<MulticastMemberships>,
+    err_queue: SpinLock<ErrorQueue>,
 
     is_nonblocking: AtomicBool,
     pollee: Pollee,
@@ -51,19 +62,45 @@ struct OptionSet {
 }
 
 impl OptionSet {
-    fn new() -> Self {
+    fn new(family: IpFamily) -> Self {
         let socket = SocketOptionSet::new_udp();
-        let ip = IpOptionSet::new_udp();
+        let ip = IpOptionSet::new_udp(family);
         OptionSet { socket, ip }
     }
+
+    fn bind_options(&self) -> BindOptions {
+        BindOptions::new(&self.socket, &self.ip)
+    }
+
+    /// Creates the control messages to be received along with a packet.
+    fn recv_control_messages(
+        &self,
+        iface: &Iface,
+        recv_info: &UdpRecvInfo,
+    ) -> Vec<ControlMessage> {
+        let mut ctrl_msgs =
+            TimestampControlMessage::new_all(&self.socket, recv_info.timestamp, false);
+
+        match recv_info.dst_addr {
+            IpAddress::Ipv4(dst_addr) if self.ip.pktinfo() => {
+                let ctrl_msg = IpControlMessage::new_pktinfo(iface, dst_addr);
+                ctrl_msgs.push(ControlMessage::Ip(ctrl_msg));
+            }
+            _ => (),
+        }
+
+        ctrl_msgs
+    }
 }
 
 impl DatagramSocket {
-    pub fn new(is_nonblocking: bool) -> Arc<Self> {
+    pub fn new(is_nonblocking: bool, family: IpFamily) -> Arc<Self> {
         let unbound_datagram = UnboundDatagram::new();
         Arc::new(Self {
             inner: RwMutex::new(Inner::Unbound(unbound_datagram)),
-            options: RwLock::new(OptionSet::new()),
+            options: RwLock::new(OptionSet::new(family)),
+            memberships: Mutex::new(MulticastMemberships::new()),
+            err_queue: SpinLock::new(ErrorQueue::new()),
             is_nonblocking: AtomicBool::new(is_nonblocking),
             pollee: Pollee::new(),
             pseudo_path: SockFs::new_path(),
@@ -74,15 +111,21 @@ impl DatagramSocket {
         &self,
         writer: &mut dyn MultiWrite,
         flags: SendRecvFlags,
-    ) -> Result<(usize, SocketAddr)> {
-        let recv_bytes = self
-            .inner
-            .read()
-            .try_recv(writer, flags)
-            .map(|(recv_bytes, remote_endpoint)| (recv_bytes, remote_endpoint.into()))?;
+    ) -> Result<(usize, SocketAddr, Vec<ControlMessage>)> {
+        let inner = self.inner.read();
+        let Inner::Bound(bound_datagram) = &*inner else {
+            return_errno_with_message!(Errno::EAGAIN, "the socket is not bound");
+        };
+
+        let (recv_bytes, remote_endpoint, recv_info) =
+            bound_datagram.try_recv_with_info(writer, flags)?;
         self.pollee.invalidate();
 
-        Ok(recv_bytes)
+        let options = self.options.read();
+        let peer_addr = options.ip.family().socket_addr_from(remote_endpoint);
+        let control_messages = options.recv_control_messages(bound_datagram.iface(), &recv_info);
+
+        Ok((recv_bytes, peer_addr, control_messages))
     }
 
     fn try_send(
@@ -101,11 +144,24 @@ impl DatagramSocket {
                         "the destination address is not specified",
                     )
                 })?;
+                let bind_options = self.options.read().bind_options();
                 self.inner
                     .write()
-                    .bind_ephemeral(remote_endpoint, &self.pollee)
+                    .bind_ephemeral(remote_endpoint, &self.pollee, bind_options)
             },
             |bound_datagram, remote_endpoint| {
+                // Like Linux, the TTL and the multicast options take effect when the packets are
+                // sent.
+                let (ttl, multicast_ttl, multicast_loop) = {
+                    let options = self.options.read();
+                    (
+                        options.ip.ttl().get(),
+                        options.ip.multicast_ttl(),
+                        options.ip.multicast_loop(),
+                    )
+                };
+                bound_datagram.set_send_options(ttl, multicast_ttl, multicast_loop);
+
                 let sent_bytes = bound_datagram.try_send(reader, remote_endpoint, flags)?;
                 let iface_to_poll = bound_datagram.iface().clone();
                 Ok((sent_bytes, iface_to_poll))
@@ -117,12 +173,27 @@ impl DatagramSocket {
 
         Ok(sent_bytes)
     }
+
+    fn try_recv_err(&self) -> Result<(usize, MessageHeader)> {
+        let options = self.options.read();
+        let result = self
+            .err_queue
+            .lock()
+            .try_recv(options.ip.family(), &options.socket);
+        self.pollee.invalidate();
+
+        result
+    }
+
+    fn check_io_events(&self) -> IoEvents {
+        self.inner.read().check_io_events() | self.err_queue.lock().check_io_events()
+    }
 }
 
 impl Pollable for DatagramSocket {
     fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
         self.pollee
-            .poll_with(mask, poller, || self.inner.read().check_io_events())
+            .poll_with(mask, poller, || self.check_io_events())
     }
 }
 
@@ -138,17 +209,25 @@ impl SocketPrivate for DatagramSocket {
 
 impl Socket for DatagramSocket {
     fn bind(&self, socket_addr: SocketAddr) -> Result<()> {
-        let endpoint = socket_addr.try_into()?;
-        let can_reuse = self.options.read().socket.reuse_addr();
+        let (endpoint, bind_options) = {
+            let options = self.options.read();
+            (options.ip.endpoint_from(socket_addr)?, options.bind_options())
+        };
 
         self.inner
             .write()
-            .bind(&endpoint, &self.pollee, BindOptions { can_reuse })
+            .bind(&endpoint, &self.pollee, bind_options)
     }
 
     fn connect(&self, socket_addr: SocketAddr) -> Result<()> {
-        let endpoint = socket_addr.try_into()?;
-        let can_broadcast = self.options.read().socket.broadcast();
+        let (endpoint, can_broadcast, bind_options) = {
+            let options = self.options.read();
+            (
+                options.ip.endpoint_from(socket_addr)?,
+                options.socket.broadcast(),
+                options.bind_options(),
+            )
+        };
         if !can_broadcast && is_broadcast_endpoint(&endpoint) {
             return_errno_with_message!(
                 Errno::EACCES,
@@ -156,17 +235,20 @@ impl Socket for DatagramSocket {
             );
         }
 
-        self.inner.write().connect(&endpoint, &self.pollee)
+        self.inner
+            .write()
+            .connect(&endpoint, &self.pollee, bind_options)
     }
 
     fn addr(&self) -> Result<SocketAddr> {
+        let family = self.options.read().ip.family();
         let endpoint = self
             .inner
             .read()
             .addr()
-            .unwrap_or(UNSPECIFIED_LOCAL_ENDPOINT);
+            .unwrap_or(family.unspecified_local_endpoint());
 
-        Ok(endpoint.into())
+        Ok(family.socket_addr_from(endpoint))
     }
 
     fn peer_addr(&self) -> Result<SocketAddr> {
@@ -175,7 +257,8 @@ impl Socket for DatagramSocket {
                 Error::with_message(Errno::ENOTCONN, "the socket is not connected")
             })?;
 
-        Ok(endpoint.into())
+        let family = self.options.read().ip.family();
+        Ok(family.socket_addr_from(endpoint))
     }
 
     fn sendmsg(
@@ -195,7 +278,7 @@ impl Socket for DatagramSocket {
         } = message_header;
 
         let endpoint = match addr {
-            Some(addr) => Some(addr.try_into()?),
+            Some(addr) => Some(self.options.read().ip.endpoint_from(addr)?),
             None => None,
         };
 
@@ -214,8 +297,30 @@ impl Socket for DatagramSocket {
             warn!("sending control message is not supported");
         }
 
+        let (is_zerocopy, timestamping) = {
+            let options = self.options.read();
+            let is_zerocopy =
+                flags.contains(SendRecvFlags::MSG_ZEROCOPY) && options.socket.zerocopy();
+            (is_zerocopy, options.socket.timestamping())
+        };
+
         // TODO: Block if the send buffer is full
-        self.try_send(reader, endpoint.as_ref(), flags)
+        let sent_bytes = self.try_send(reader, endpoint.as_ref(), flags)?;
+
+        let has_err = {
+            let mut err_queue = self.err_queue.lock();
+            if is_zerocopy {
+                err_queue.complete_zerocopy_send();
+            }
+            // Each datagram takes one key.
+            let has_tx_timestamps = err_queue.record_tx_timestamps(timestamping, 1);
+            is_zerocopy || has_tx_timestamps
+        };
+        if has_err {
+            self.pollee.notify(IoEvents::ERR);
+        }
+
+        Ok(sent_bytes)
     }
 
     fn recvmsg(
@@ -228,12 +333,14 @@ impl Socket for DatagramSocket {
             warn!("unsupported flags: {:?}", flags);
         }
 
-        let (received_bytes, peer_addr) =
-            self.block_on(IoEvents::IN, || self.try_recv(writer, flags))?;
+        if flags.contains(SendRecvFlags::MSG_ERRQUEUE) {
+            return self.try_recv_err();
+        }
 
-        // TODO: Receive control message
+        let (received_bytes, peer_addr, control_messages) =
+            self.block_on_with_flags(IoEvents::IN, flags, || self.try_recv(writer, flags))?;
 
-        let message_header = MessageHeader::new(Some(peer_addr), Vec::new());
+        let message_header = MessageHeader::new(Some(peer_addr), control_messages);
 
         Ok((received_bytes, message_header))
     }
@@ -262,6 +369,18 @@ impl Socket for DatagramSocket {
     }
 
     fn set_option(&self, option: &dyn SocketOption) -> Result<()> {
+        sock_option_ref!(match option {
+            add_membership @ AddMembership => {
+                let request = add_membership.get().unwrap();
+                return self.memberships.lock().join(request);
+            }
+            drop_membership @ DropMembership => {
+                let request = drop_membership.get().unwrap();
+                return self.memberships.lock().leave(request);
+            }
+            _ => (),
+        });
+
         let inner = self.inner.read();
         let mut options = self.options.write();
 
@@ -311,6 +430,18 @@ impl SetSocketLevelOption for Inner<UnboundDatagram, BoundDatagram> {
 
         bound.bound_port().set_can_reuse(reuse_addr);
     }
+
+    fn set_reuse_port(&self, reuse_port: bool) {
+        let Inner::Bound(bound) = self else {
+            return;
+        };
+
+        bound.bound_port().set_can_reuse_port(reuse_port);
+    }
+
+    fn set_zerocopy(&self, _zerocopy: bool) -> Result<()> {
+        Ok(())
+    }
 }
 
 impl SetIpLevelOption for Inner<UnboundDatagram, BoundDatagram> {
@@ -320,4 +451,14 @@ impl SetIpLevelOption for Inner<UnboundDatagram, BoundDatagram> {
             "IP_HDRINCL cannot be set on UDP sockets"
         );
     }
+
+    fn set_v6only(&self, _v6only: bool) -> Result<()> {
+        if matches!(self, Inner::Bound(_)) {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "IPV6_V6ONLY cannot be set on bound sockets"
+            );
+        }
+        Ok(())
+    }
 }
@@ -1,12 +1,12 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use aster_bigtcp::{socket::UdpSocket, wire::IpEndpoint};
+use aster_bigtcp::{iface::PortReuse, socket::UdpSocket, wire::IpEndpoint};
 
 use super::{bound::BoundDatagram, observer::DatagramObserver};
 use crate::{
     events::IoEvents,
     net::socket::{
-        ip::common::{bind_port, get_ephemeral_endpoint},
+        ip::common::{BindOptions, bind_port, get_ephemeral_endpoint},
         util::datagram_common,
     },
     prelude::*,
@@ -23,10 +23,6 @@ impl UnboundDatagram {
     }
 }
 
-pub(super) struct BindOptions {
-    pub(super) can_reuse: bool,
-}
-
 impl datagram_common::Unbound for UnboundDatagram {
     type Endpoint = IpEndpoint;
     type BindOptions = BindOptions;
@@ -39,7 +35,7 @@ impl datagram_common::Unbound for UnboundDatagram {
         pollee: &Pollee,
         options: BindOptions,
     ) -> Result<Self::Bound> {
-        let bound_port = bind_port(endpoint, options.can_reuse)?;
+        let bound_port = bind_port(endpoint, &options)?;
 
         let bound_socket =
             match UdpSocket::new_bind(bound_port, DatagramObserver::new(pollee.clone())) {
@@ -56,9 +52,14 @@ impl datagram_common::Unbound for UnboundDatagram {
         &mut self,
         remote_endpoint: &Self::Endpoint,
         pollee: &Pollee,
+        options: BindOptions,
     ) -> Result<Self::Bound> {
-        let endpoint = get_ephemeral_endpoint(remote_endpoint);
-        self.bind(&endpoint, pollee, BindOptions { can_reuse: false })
+        let endpoint = get_ephemeral_endpoint(remote_endpoint, &options)?;
+        let options = BindOptions {
+            reuse: PortReuse::empty(),
+            ..options
+        };
+        self.bind(&endpoint, pollee, options)
     }
 
     fn check_io_events(&self) -> IoEvents {
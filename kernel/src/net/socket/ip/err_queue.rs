@@ -0,0 +1,189 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use super::{IpFamily, ctrl_msg::IpControlMessage};
+use crate::{
+    events::IoEvents,
+    net::socket::util::{
+        ControlMessage, MessageHeader, TimestampControlMessage, TimestampingFlags,
+        options::SocketOptionSet,
+    },
+    prelude::*,
+    time::{Clock, clocks::RealTimeClock},
+};
+
+/// The error queue of a socket, from which messages can be received with `MSG_ERRQUEUE`.
+///
+/// The queue holds two kinds of messages:
+///  - The completion notifications of zero-copy transmissions (`MSG_ZEROCOPY`). Each zero-copy
+///    send is assigned a 32-bit ID in sequence. When the send completes, its ID is reported on the
+///    error queue. Like Linux, the notifications of consecutive IDs are merged into a single one.
+///  - The transmit timestamps (`SO_TIMESTAMPING`). Only software timestamps are supported.
+pub(super) struct ErrorQueue {
+    next_zerocopy_id: u32,
+    /// The key of the next transmit timestamp.
+    ///
+    /// This is `None` if the timestamps are not identified by keys (i.e., if
+    /// `SOF_TIMESTAMPING_OPT_ID` is not enabled).
+    next_tx_key: Option<u32>,
+    queue: VecDeque<Entry>,
+}
+
+enum Entry {
+    ZerocopyCompletion(IdRange),
+    TxTimestamp(TxTimestamp),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct IdRange {
+    first: u32,
+    len: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TxTimestamp {
+    timestamp: Duration,
+    type_: u32,
+    key: u32,
+}
+
+impl ErrorQueue {
+    /// Creates a new empty error queue.
+    pub(super) const fn new() -> Self {
+        Self {
+            next_zerocopy_id: 0,
+            next_tx_key: None,
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Allocates an ID for a completed zero-copy send and queues the notification.
+    ///
+    /// FIXME: The user pages are not pinned and transmitted in place. The data is still copied to
+    /// the send buffer, so the send completes immediately and the notification reports
+    /// `SO_EE_CODE_ZEROCOPY_COPIED`. This is what Linux reports when zero-copy transmission is not
+    /// possible (e.g., on the loopback device), so applications can still work correctly.
+    pub(super) fn complete_zerocopy_send(&mut self) {
+        let id = self.next_zerocopy_id;
+        self.next_zerocopy_id = id.wrapping_add(1);
+
+        // Reference: <https://elixir.bootlin.com/linux/v6.13/source/net/core/skbuff.c#L1723>.
+        let mergeable = self
+            .queue
+            .back_mut()
+            .and_then(|entry| match entry {
+                Entry::ZerocopyCompletion(range) => Some(range),
+                Entry::TxTimestamp(_) => None,
+            })
+            .filter(|last| last.first.wrapping_add(last.len) == id && last.len < u32::MAX);
+        if let Some(last) = mergeable {
+            last.len += 1;
+            return;
+        }
+
+        self.queue
+            .push_back(Entry::ZerocopyCompletion(IdRange { first: id, len: 1 }));
+    }
+
+    /// Queues the transmit timestamps of a send, if they are requested by `flags`.
+    ///
+    /// With `SOF_TIMESTAMPING_OPT_ID`, the send takes `nr_keys` keys (one per datagram for UDP
+    /// sockets, or one per byte for TCP sockets), and its timestamps are identified by the last
+    /// key. `nr_keys` must not be zero.
+    ///
+    /// This method returns whether any timestamps are queued.
+    pub(super) fn record_tx_timestamps(&mut self, flags: TimestampingFlags, nr_keys: u32) -> bool {
+        debug_assert_ne!(nr_keys, 0);
+
+        // Like Linux, the keys restart from zero when `SOF_TIMESTAMPING_OPT_ID` is enabled. We
+        // reset them lazily here, which makes no difference unless the flag is disabled and
+        // enabled again between two sends.
+        let key = if flags.contains(TimestampingFlags::OPT_ID) {
+            let first_key = self.next_tx_key.unwrap_or(0);
+            self.next_tx_key = Some(first_key.wrapping_add(nr_keys));
+            first_key.wrapping_add(nr_keys - 1)
+        } else {
+            self.next_tx_key = None;
+            0
+        };
+
+        // We have no queueing disciplines and the packets are handed over to the device as soon
+        // as they are sent, so the timestamps of both events are taken now.
+        //
+        // TODO: Support `SOF_TIMESTAMPING_TX_ACK`, which requires the TCP stack to report when the
+        // data is acknowledged.
+        let timestamp = RealTimeClock::get().read_time();
+        let mut is_recorded = false;
+
+        if flags.contains(TimestampingFlags::TX_SCHED) {
+            self.queue.push_back(Entry::TxTimestamp(TxTimestamp {
+                timestamp,
+                type_: SCM_TSTAMP_SCHED,
+                key,
+            }));
+            is_recorded = true;
+        }
+
+        if flags.contains(TimestampingFlags::TX_SOFTWARE) {
+            self.queue.push_back(Entry::TxTimestamp(TxTimestamp {
+                timestamp,
+                type_: SCM_TSTAMP_SND,
+                key,
+            }));
+            is_recorded = true;
+        }
+
+        is_recorded
+    }
+
+    /// Dequeues a message and builds it to be received with `MSG_ERRQUEUE`.
+    pub(super) fn try_recv(
+        &mut self,
+        family: IpFamily,
+        options: &SocketOptionSet,
+    ) -> Result<(usize, MessageHeader)> {
+        let Some(entry) = self.queue.pop_front() else {
+            return_errno_with_message!(Errno::EAGAIN, "the error queue is empty");
+        };
+
+        let control_messages = match entry {
+            Entry::ZerocopyCompletion(range) => {
+                let last_id = range.first.wrapping_add(range.len - 1);
+                let ctrl_msg =
+                    IpControlMessage::new_zerocopy_completion(family, range.first, last_id);
+                vec![ControlMessage::Ip(ctrl_msg)]
+            }
+            Entry::TxTimestamp(TxTimestamp {
+                timestamp,
+                type_,
+                key,
+            }) => {
+                let mut ctrl_msgs = TimestampControlMessage::new_all(options, timestamp, true);
+                let ctrl_msg = IpControlMessage::new_tx_timestamp(family, type_, key);
+                ctrl_msgs.push(ControlMessage::Ip(ctrl_msg));
+                ctrl_msgs
+            }
+        };
+
+        // FIXME: Without `SOF_TIMESTAMPING_OPT_TSONLY`, Linux returns the sent packet along with
+        // its transmit timestamps. We always return no payload, as if the flag is set.
+        let message_header = MessageHeader::new(None, control_messages);
+
+        Ok((0, message_header))
+    }
+
+    /// Returns `IoEvents::ERR` if there are pending messages.
+    pub(super) fn check_io_events(&self) -> IoEvents {
+        if self.queue.is_empty() {
+            IoEvents::empty()
+        } else {
+            IoEvents::ERR
+        }
+    }
+}
+
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/errqueue.h#L52>.
+const SCM_TSTAMP_SND: u32 = 0;
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/errqueue.h#L53>.
+const SCM_TSTAMP_SCHED: u32 = 1;
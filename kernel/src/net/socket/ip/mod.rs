@@ -2,11 +2,19 @@
 
 mod addr;
 mod common;
+mod ctrl_msg;
 mod datagram;
+mod err_queue;
+mod multicast;
 pub mod options;
+mod raw;
 mod stream;
 
+pub use addr::IpFamily;
+pub(super) use ctrl_msg::IpControlMessage;
 pub use datagram::DatagramSocket;
 pub(in crate::net) use datagram::observer::DatagramObserver;
+pub use raw::{RawSocket, options as raw_options};
+pub(super) use stream::TlsControlMessage;
 pub(in crate::net) use stream::observer::StreamObserver;
 pub use stream::{StreamSocket, options as stream_options};
@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_bigtcp::wire::Ipv4Address;
+
+use super::{common::get_multicast_iface, options::IpMembershipRequest};
+use crate::{net::iface::Iface, prelude::*};
+
+/// The IPv4 multicast groups joined by a socket.
+///
+/// When dropped, the socket leaves all the groups.
+pub(super) struct MulticastMemberships(Vec<Membership>);
+
+/// The maximum number of groups that a socket can join.
+///
+/// This is the default value of `net.ipv4.igmp_max_memberships` in Linux.
+const MAX_MEMBERSHIPS: usize = 20;
+
+impl MulticastMemberships {
+    pub(super) const fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Joins the multicast group on the iface specified in the request.
+    pub(super) fn join(&mut self, request: &IpMembershipRequest) -> Result<()> {
+        check_group_addr(request.group_addr)?;
+        let iface = get_multicast_iface(request)?;
+
+        if self.0.iter().any(|membership| {
+            Arc::ptr_eq(&membership.iface, &iface) && membership.group_addr == request.group_addr
+        }) {
+            return_errno_with_message!(Errno::EADDRINUSE, "the group has already been joined");
+        }
+        if self.0.len() >= MAX_MEMBERSHIPS {
+            return_errno_with_message!(Errno::ENOBUFS, "too many groups have been joined");
+        }
+
+        self.0.push(Membership::new(iface, request.group_addr));
+
+        Ok(())
+    }
+
+    /// Leaves the multicast group on the iface specified in the request.
+    ///
+    /// Like Linux, if the request does not specify an iface, the group is left on any iface that
+    /// has joined it.
+    pub(super) fn leave(&mut self, request: &IpMembershipRequest) -> Result<()> {
+        check_group_addr(request.group_addr)?;
+
+        let Some(index) = self.0.iter().position(|membership| {
+            membership.group_addr == request.group_addr && membership.is_on_iface_of(request)
+        }) else {
+            return_errno_with_message!(Errno::EADDRNOTAVAIL, "the group has not been joined");
+        };
+
+        self.0.swap_remove(index);
+
+        Ok(())
+    }
+}
+
+fn check_group_addr(group_addr: Ipv4Address) -> Result<()> {
+    if !group_addr.is_multicast() {
+        return_errno_with_message!(Errno::EINVAL, "the group address is not a multicast address");
+    }
+    Ok(())
+}
+
+/// A membership of an IPv4 multicast group on an iface.
+///
+/// When dropped, the iface leaves the group if no other sockets are still members.
+struct Membership {
+    iface: Arc<Iface>,
+    group_addr: Ipv4Address,
+}
+
+impl Membership {
+    fn new(iface: Arc<Iface>, group_addr: Ipv4Address) -> Self {
+        // Poll the iface to send the IGMP report as soon as possible.
+        if *iface.join_ipv4_multicast_group(group_addr) {
+            iface.poll();
+        }
+
+        Self { iface, group_addr }
+    }
+
+    /// Returns whether the membership is on the iface specified in the request, if any.
+    fn is_on_iface_of(&self, request: &IpMembershipRequest) -> bool {
+        if request.interface_index != 0 {
+            self.iface.index() == request.interface_index
+        } else if !request.interface_addr.is_unspecified() {
+            self.iface.ipv4_addr() == Some(request.interface_addr)
+        } else {
+            true
+        }
+    }
+}
+
+impl Drop for Membership {
+    fn drop(&mut self) {
+        // Poll the iface to send the IGMP leave message as soon as possible.
+        if *self.iface.leave_ipv4_multicast_group(self.group_addr) {
+            self.iface.poll();
+        }
+    }
+}
@@ -2,49 +2,107 @@
 
 use core::num::NonZeroU8;
 
-use aster_bigtcp::socket::NeedIfacePoll;
+use aster_bigtcp::{
+    socket::NeedIfacePoll,
+    wire::{IpEndpoint, Ipv4Address},
+};
 
+use super::addr::IpFamily;
 use crate::{
-    net::socket::options::{
-        SocketOption,
-        macros::{impl_socket_options, sock_option_mut, sock_option_ref},
+    net::socket::{
+        options::{
+            SocketOption,
+            macros::{impl_socket_options, sock_option_mut, sock_option_ref},
+        },
+        util::SocketAddr,
     },
     prelude::*,
 };
 
 /// IP-level socket options.
+///
+/// This also includes the IPv6-level socket options, which are only available for `AF_INET6`
+/// sockets.
 #[derive(Debug, Clone, Copy, CopyGetters, Setters)]
 #[get_copy = "pub"]
-#[set = "pub"]
 pub(super) struct IpOptionSet {
+    #[set = "pub"]
     tos: u8,
+    #[set = "pub"]
     ttl: IpTtl,
+    #[set = "pub"]
     hdrincl: bool,
+    #[set = "pub"]
     recverr: bool,
+    #[set = "pub"]
+    pktinfo: bool,
+    #[set = "pub"]
+    freebind: bool,
+    #[set = "pub"]
+    v6only: bool,
+    #[set = "pub"]
+    multicast_ttl: u8,
+    #[set = "pub"]
+    multicast_loop: bool,
+    family: IpFamily,
 }
 
 const DEFAULT_TTL: u8 = 64;
+const DEFAULT_MULTICAST_TTL: u8 = 1;
 pub(super) const INET_ECN_MASK: u8 = 3;
 
 impl IpOptionSet {
-    pub(super) const fn new_tcp() -> Self {
+    pub(super) const fn new_tcp(family: IpFamily) -> Self {
         Self {
             tos: 0,
             ttl: IpTtl(None),
             hdrincl: false,
             recverr: false,
+            pktinfo: false,
+            freebind: false,
+            v6only: false,
+            multicast_ttl: DEFAULT_MULTICAST_TTL,
+            multicast_loop: true,
+            family,
         }
     }
 
-    pub(super) const fn new_udp() -> Self {
+    pub(super) const fn new_udp(family: IpFamily) -> Self {
         Self {
             tos: 0,
             ttl: IpTtl(None),
             hdrincl: false,
             recverr: false,
+            pktinfo: false,
+            freebind: false,
+            v6only: false,
+            multicast_ttl: DEFAULT_MULTICAST_TTL,
+            multicast_loop: true,
+            family,
+        }
+    }
+
+    pub(super) const fn new_raw(hdrincl: bool) -> Self {
+        Self {
+            tos: 0,
+            ttl: IpTtl(None),
+            hdrincl,
+            recverr: false,
+            pktinfo: false,
+            freebind: false,
+            v6only: false,
+            multicast_ttl: DEFAULT_MULTICAST_TTL,
+            multicast_loop: true,
+            family: IpFamily::Ipv4,
         }
     }
 
+    /// Converts a socket address to an IP endpoint according to the address family and the
+    /// `IPV6_V6ONLY` option.
+    pub(super) fn endpoint_from(&self, socket_addr: SocketAddr) -> Result<IpEndpoint> {
+        self.family.endpoint_from(socket_addr, self.v6only)
+    }
+
     pub(super) fn get_option(&self, option: &mut dyn SocketOption) -> Result<()> {
         sock_option_mut!(match option {
             ip_tos @ Tos => {
@@ -63,6 +121,27 @@ impl IpOptionSet {
                 let recverr = self.recverr();
                 ip_recverr.set(recverr);
             }
+            ip_pktinfo @ Pktinfo => {
+                let pktinfo = self.pktinfo();
+                ip_pktinfo.set(pktinfo);
+            }
+            ip_freebind @ Freebind => {
+                let freebind = self.freebind();
+                ip_freebind.set(freebind);
+            }
+            ipv6_v6only @ V6Only => {
+                self.check_ipv6_option()?;
+                let v6only = self.v6only();
+                ipv6_v6only.set(v6only);
+            }
+            ip_multicast_ttl @ MulticastTtl => {
+                let multicast_ttl = self.multicast_ttl();
+                ip_multicast_ttl.set(multicast_ttl as _);
+            }
+            ip_multicast_loop @ MulticastLoop => {
+                let multicast_loop = self.multicast_loop();
+                ip_multicast_loop.set(multicast_loop);
+            }
             _ => return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option is unknown"),
         });
 
@@ -84,6 +163,7 @@ impl IpOptionSet {
             }
             ip_ttl @ Ttl => {
                 let ttl = ip_ttl.get().unwrap();
+                socket.set_ttl(ttl.get());
                 self.set_ttl(*ttl);
             }
             ip_hdrincl @ Hdrincl => {
@@ -95,6 +175,32 @@ impl IpOptionSet {
                 let recverr = ip_recverr.get().unwrap();
                 self.set_recverr(*recverr);
             }
+            ip_pktinfo @ Pktinfo => {
+                let pktinfo = ip_pktinfo.get().unwrap();
+                self.set_pktinfo(*pktinfo);
+            }
+            ip_freebind @ Freebind => {
+                let freebind = ip_freebind.get().unwrap();
+                self.set_freebind(*freebind);
+            }
+            ipv6_v6only @ V6Only => {
+                self.check_ipv6_option()?;
+                let v6only = ipv6_v6only.get().unwrap();
+                socket.set_v6only(*v6only)?;
+                self.set_v6only(*v6only);
+            }
+            ip_multicast_ttl @ MulticastTtl => {
+                let multicast_ttl = match *ip_multicast_ttl.get().unwrap() {
+                    -1 => DEFAULT_MULTICAST_TTL,
+                    val @ 0..=255 => val as u8,
+                    _ => return_errno_with_message!(Errno::EINVAL, "the multicast TTL is invalid"),
+                };
+                self.set_multicast_ttl(multicast_ttl);
+            }
+            ip_multicast_loop @ MulticastLoop => {
+                let multicast_loop = ip_multicast_loop.get().unwrap();
+                self.set_multicast_loop(*multicast_loop);
+            }
             _ => return_errno_with_message!(
                 Errno::ENOPROTOOPT,
                 "the socket option to be set is unknown"
@@ -103,6 +209,16 @@ impl IpOptionSet {
 
         Ok(NeedIfacePoll::FALSE)
     }
+
+    fn check_ipv6_option(&self) -> Result<()> {
+        if self.family != IpFamily::Ipv6 {
+            return_errno_with_message!(
+                Errno::ENOPROTOOPT,
+                "IPv6-level options are only available for IPv6 sockets"
+            );
+        }
+        Ok(())
+    }
 }
 
 impl_socket_options!(
@@ -110,6 +226,13 @@ impl_socket_options!(
     pub struct Ttl(IpTtl);
     pub struct Hdrincl(bool);
     pub struct Recverr(bool);
+    pub struct Pktinfo(bool);
+    pub struct Freebind(bool);
+    pub struct V6Only(bool);
+    pub struct MulticastTtl(i32);
+    pub struct MulticastLoop(bool);
+    pub struct AddMembership(IpMembershipRequest);
+    pub struct DropMembership(IpMembershipRequest);
 );
 
 #[derive(Debug, Clone, Copy)]
@@ -129,6 +252,28 @@ impl IpTtl {
     }
 }
 
+/// A request to join or leave an IPv4 multicast group.
+///
+/// This corresponds to `struct ip_mreqn` in Linux, which extends `struct ip_mreq` with the
+/// interface index.
+#[derive(Debug, Clone, Copy)]
+pub struct IpMembershipRequest {
+    /// The address of the multicast group.
+    pub group_addr: Ipv4Address,
+    /// The address of the local interface, or the unspecified address to ignore it.
+    pub interface_addr: Ipv4Address,
+    /// The index of the local interface, or zero to ignore it.
+    pub interface_index: u32,
+}
+
 pub(super) trait SetIpLevelOption {
     fn set_hdrincl(&self, _hdrincl: bool) -> Result<()>;
+
+    fn set_v6only(&self, _v6only: bool) -> Result<()>;
+
+    /// Sets the TTL of the outgoing unicast packets.
+    ///
+    /// This does nothing by default, which is the case for sockets that apply the TTL when
+    /// sending packets.
+    fn set_ttl(&self, _ttl: u8) {}
 }
@@ -0,0 +1,141 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_bigtcp::{
+    errors::raw::{RecvError, SendError},
+    socket::RawIpSocketKind,
+    wire::{IpAddress, IpEndpoint},
+};
+
+use crate::{
+    events::IoEvents,
+    net::{
+        iface::{Iface, RawIpSocket},
+        socket::util::{SendRecvFlags, datagram_common},
+    },
+    prelude::*,
+    util::{MultiRead, MultiWrite},
+};
+
+pub(super) struct BoundRaw {
+    bound_socket: RawIpSocket,
+    remote_endpoint: Option<IpEndpoint>,
+}
+
+impl BoundRaw {
+    pub(super) fn new(bound_socket: RawIpSocket) -> Self {
+        Self {
+            bound_socket,
+            remote_endpoint: None,
+        }
+    }
+
+    pub(super) fn iface(&self) -> &Arc<Iface> {
+        self.bound_socket.iface()
+    }
+
+    pub(super) fn is_ping(&self) -> bool {
+        matches!(self.bound_socket.kind(), RawIpSocketKind::Ping(_))
+    }
+
+    pub(super) fn set_icmp_filter(&self, icmp_filter: u32) {
+        self.bound_socket.set_icmp_filter(icmp_filter);
+    }
+
+    pub(super) fn set_send_options(&self, ttl: u8, hdrincl: bool) {
+        self.bound_socket.set_hop_limit(ttl);
+        self.bound_socket.set_header_included(hdrincl);
+    }
+}
+
+impl datagram_common::Bound for BoundRaw {
+    type Endpoint = IpEndpoint;
+
+    fn local_endpoint(&self) -> Self::Endpoint {
+        // Like Linux, the port is the protocol number for raw sockets and the identifier for ping
+        // sockets.
+        let port = match self.bound_socket.kind() {
+            RawIpSocketKind::Raw(protocol) => u8::from(protocol) as u16,
+            RawIpSocketKind::Ping(ident) => ident,
+        };
+        IpEndpoint::new(IpAddress::Ipv4(self.bound_socket.local_addr()), port)
+    }
+
+    fn remote_endpoint(&self) -> Option<&Self::Endpoint> {
+        self.remote_endpoint.as_ref()
+    }
+
+    fn set_remote_endpoint(&mut self, endpoint: &Self::Endpoint) {
+        let IpAddress::Ipv4(remote_addr) = endpoint.addr else {
+            unreachable!("the socket has been bound to an IPv4 iface");
+        };
+        self.bound_socket.connect(remote_addr);
+        self.remote_endpoint = Some(*endpoint);
+    }
+
+    fn try_recv(
+        &self,
+        writer: &mut dyn MultiWrite,
+        _flags: SendRecvFlags,
+    ) -> Result<(usize, Self::Endpoint)> {
+        let result = self.bound_socket.recv(|packet, src_addr| {
+            let copied_res = writer.write(&mut VmReader::from(packet));
+            // Like Linux, the port of the source address is always zero.
+            let endpoint = IpEndpoint::new(IpAddress::Ipv4(src_addr), 0);
+            (copied_res, endpoint)
+        });
+
+        match result {
+            Ok((Ok(res), endpoint)) => Ok((res, endpoint)),
+            Ok((Err(e), _)) => Err(e),
+            Err(RecvError::Exhausted) => {
+                return_errno_with_message!(Errno::EAGAIN, "the receive buffer is empty")
+            }
+        }
+    }
+
+    fn try_send(
+        &self,
+        reader: &mut dyn MultiRead,
+        remote: &Self::Endpoint,
+        _flags: SendRecvFlags,
+    ) -> Result<usize> {
+        let IpAddress::Ipv4(remote_addr) = remote.addr else {
+            return_errno_with_message!(Errno::EAFNOSUPPORT, "only IPv4 addresses are supported");
+        };
+
+        let len = reader.sum_lens();
+        if len > u16::MAX as usize {
+            return_errno_with_message!(Errno::EMSGSIZE, "the message is too large");
+        }
+
+        let mut packet = vec![0u8; len];
+        reader.read(&mut VmWriter::from(packet.as_mut_slice()))?;
+
+        match self.bound_socket.send(remote_addr, packet) {
+            Ok(()) => Ok(len),
+            Err(SendError::TooLarge) => {
+                return_errno_with_message!(Errno::EMSGSIZE, "the message is too large");
+            }
+            Err(SendError::InvalidHeader) => {
+                return_errno_with_message!(Errno::EINVAL, "the packet header is invalid");
+            }
+            Err(SendError::BufferFull) => {
+                return_errno_with_message!(Errno::EAGAIN, "the send buffer is full");
+            }
+        }
+    }
+
+    fn check_io_events(&self) -> IoEvents {
+        let mut events = IoEvents::empty();
+
+        if self.bound_socket.can_recv() {
+            events |= IoEvents::IN;
+        }
+
+        if self.bound_socket.can_send() {
+            events |= IoEvents::OUT;
+        }
+
+        events
+    }
+}
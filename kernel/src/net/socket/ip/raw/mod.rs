@@ -0,0 +1,439 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use aster_bigtcp::wire::{IpAddress, IpEndpoint, IpProtocol, Ipv4Address};
+use bound::BoundRaw;
+use options::{IcmpFilter, PingGroupRange};
+use unbound::UnboundRaw;
+
+use crate::{
+    events::IoEvents,
+    fs::{pseudofs::SockFs, vfs::path::Path},
+    net::socket::{
+        Socket,
+        ip::options::{IpOptionSet, SetIpLevelOption},
+        options::{
+            Error as SocketError, SocketOption,
+            macros::{sock_option_mut, sock_option_ref},
+        },
+        private::SocketPrivate,
+        util::{
+            MessageHeader, SendRecvFlags, SocketAddr,
+            datagram_common::{Inner, select_remote_and_bind},
+            options::{GetSocketLevelOption, SetSocketLevelOption, SocketOptionSet},
+        },
+    },
+    prelude::*,
+    process::{
+        credentials::capabilities::CapSet,
+        posix_thread::AsPosixThread,
+        signal::{PollHandle, Pollable, Pollee},
+    },
+    util::{MultiRead, MultiWrite},
+};
+
+mod bound;
+pub mod options;
+mod unbound;
+
+/// A raw IPv4 socket (`SOCK_RAW`) or an ICMP datagram socket (`SOCK_DGRAM` with `IPPROTO_ICMP`).
+///
+/// The latter is also known as a ping socket, which allows unprivileged users to send ICMP echo
+/// requests if their groups are in `net.ipv4.ping_group_range`.
+pub struct RawSocket {
+    // Lock order: `inner` first, `options` second
+    inner: RwMutex<Inner<UnboundRaw, BoundRaw>>,
+    options: RwLock<OptionSet>,
+    socket_type: RawSocketType,
+
+    is_nonblocking: AtomicBool,
+    pollee: Pollee,
+    pseudo_path: Path,
+}
+
+/// The type of a [`RawSocket`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RawSocketType {
+    /// A raw socket that sends and receives the IP packets of the protocol.
+    Raw(IpProtocol),
+    /// A ping socket that sends ICMP echo requests and receives ICMP echo replies.
+    Ping,
+}
+
+#[derive(Debug, Clone)]
+struct OptionSet {
+    socket: SocketOptionSet,
+    ip: IpOptionSet,
+    icmp_filter: u32,
+}
+
+impl OptionSet {
+    fn new(socket_type: RawSocketType) -> Self {
+        let socket = SocketOptionSet::new_raw();
+        // Like Linux, `IP_HDRINCL` is enabled by default for `IPPROTO_RAW` sockets.
+        let hdrincl = match socket_type {
+            RawSocketType::Raw(protocol) => u8::from(protocol) == IPPROTO_RAW,
+            RawSocketType::Ping => false,
+        };
+        let ip = IpOptionSet::new_raw(hdrincl);
+        OptionSet {
+            socket,
+            ip,
+            icmp_filter: 0,
+        }
+    }
+}
+
+/// The protocol number of `IPPROTO_RAW`.
+const IPPROTO_RAW: u8 = 255;
+
+impl RawSocket {
+    /// Creates a raw socket for the protocol.
+    ///
+    /// Like Linux, this fails with `EPERM` if the current process does not have `CAP_NET_RAW`.
+    pub fn new_raw(is_nonblocking: bool, protocol: u8) -> Result<Arc<Self>> {
+        let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+        if !credentials.effective_capset().contains(CapSet::NET_RAW) {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "creating raw sockets requires the CAP_NET_RAW capability"
+            );
+        }
+
+        Ok(Self::new(
+            is_nonblocking,
+            RawSocketType::Raw(IpProtocol::from(protocol)),
+        ))
+    }
+
+    /// Creates a ping socket.
+    ///
+    /// Like Linux, this fails with `EACCES` if neither the effective group nor the supplementary
+    /// groups of the current process are in `net.ipv4.ping_group_range`.
+    pub fn new_ping(is_nonblocking: bool) -> Result<Arc<Self>> {
+        let range = PingGroupRange::get();
+
+        let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+        if !range.contains(credentials.egid())
+            && !credentials.groups().iter().any(|gid| range.contains(*gid))
+        {
+            return_errno_with_message!(
+                Errno::EACCES,
+                "the group is not allowed to create ping sockets"
+            );
+        }
+
+        Ok(Self::new(is_nonblocking, RawSocketType::Ping))
+    }
+
+    fn new(is_nonblocking: bool, socket_type: RawSocketType) -> Arc<Self> {
+        Arc::new(Self {
+            inner: RwMutex::new(Inner::Unbound(UnboundRaw::new(socket_type))),
+            options: RwLock::new(OptionSet::new(socket_type)),
+            socket_type,
+            is_nonblocking: AtomicBool::new(is_nonblocking),
+            pollee: Pollee::new(),
+            pseudo_path: SockFs::new_path(),
+        })
+    }
+
+    /// Applies the ICMP filter to the bound socket.
+    ///
+    /// This should be called after the socket is bound, since the filter is kept in the option
+    /// set before that.
+    fn sync_icmp_filter(&self) {
+        let inner = self.inner.read();
+        let Inner::Bound(bound_raw) = &*inner else {
+            return;
+        };
+        bound_raw.set_icmp_filter(self.options.read().icmp_filter);
+    }
+
+    fn try_recv(
+        &self,
+        writer: &mut dyn MultiWrite,
+        flags: SendRecvFlags,
+    ) -> Result<(usize, SocketAddr)> {
+        let recv_bytes = self
+            .inner
+            .read()
+            .try_recv(writer, flags)
+            .map(|(recv_bytes, remote_endpoint)| (recv_bytes, remote_endpoint.into()))?;
+        self.pollee.invalidate();
+
+        Ok(recv_bytes)
+    }
+
+    fn try_send(
+        &self,
+        reader: &mut dyn MultiRead,
+        remote: Option<&IpEndpoint>,
+        flags: SendRecvFlags,
+    ) -> Result<usize> {
+        let mut is_newly_bound = false;
+
+        let (sent_bytes, iface_to_poll) = select_remote_and_bind(
+            &self.inner,
+            remote,
+            || {
+                let remote_endpoint = remote.ok_or_else(|| {
+                    Error::with_message(
+                        Errno::EDESTADDRREQ,
+                        "the destination address is not specified",
+                    )
+                })?;
+                is_newly_bound = true;
+                self.inner
+                    .write()
+                    .bind_ephemeral(remote_endpoint, &self.pollee, ())
+            },
+            |bound_raw, remote_endpoint| {
+                // Like Linux, the TTL and `IP_HDRINCL` take effect when the packets are sent.
+                let (ttl, hdrincl) = {
+                    let options = self.options.read();
+                    (options.ip.ttl().get(), options.ip.hdrincl())
+                };
+                bound_raw.set_send_options(ttl, hdrincl);
+
+                let sent_bytes = bound_raw.try_send(reader, remote_endpoint, flags)?;
+                let iface_to_poll = bound_raw.iface().clone();
+                Ok((sent_bytes, iface_to_poll))
+            },
+        )?;
+
+        if is_newly_bound {
+            self.sync_icmp_filter();
+        }
+
+        self.pollee.invalidate();
+        iface_to_poll.poll();
+
+        Ok(sent_bytes)
+    }
+}
+
+impl Pollable for RawSocket {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.pollee
+            .poll_with(mask, poller, || self.inner.read().check_io_events())
+    }
+}
+
+impl SocketPrivate for RawSocket {
+    fn is_nonblocking(&self) -> bool {
+        self.is_nonblocking.load(Ordering::Relaxed)
+    }
+
+    fn set_nonblocking(&self, is_nonblocking: bool) {
+        self.is_nonblocking.store(is_nonblocking, Ordering::Relaxed);
+    }
+}
+
+impl Socket for RawSocket {
+    fn bind(&self, socket_addr: SocketAddr) -> Result<()> {
+        let endpoint = self.options.read().ip.endpoint_from(socket_addr)?;
+
+        let mut inner = self.inner.write();
+
+        if endpoint.addr.is_unspecified()
+            && let Inner::Unbound(unbound_raw) = &mut *inner
+        {
+            unbound_raw.bind_unspecified(endpoint.port);
+            return Ok(());
+        }
+
+        inner.bind(&endpoint, &self.pollee, ())?;
+        drop(inner);
+
+        self.sync_icmp_filter();
+
+        Ok(())
+    }
+
+    fn connect(&self, socket_addr: SocketAddr) -> Result<()> {
+        let endpoint = self.options.read().ip.endpoint_from(socket_addr)?;
+
+        self.inner.write().connect(&endpoint, &self.pollee, ())?;
+
+        self.sync_icmp_filter();
+
+        Ok(())
+    }
+
+    fn addr(&self) -> Result<SocketAddr> {
+        let endpoint = self.inner.read().addr().unwrap_or_else(|| {
+            // Like Linux, the port of an unbound raw socket is its protocol number.
+            let port = match self.socket_type {
+                RawSocketType::Raw(protocol) => u8::from(protocol) as u16,
+                RawSocketType::Ping => 0,
+            };
+            IpEndpoint::new(IpAddress::Ipv4(Ipv4Address::UNSPECIFIED), port)
+        });
+
+        Ok(endpoint.into())
+    }
+
+    fn peer_addr(&self) -> Result<SocketAddr> {
+        let endpoint =
+            *self.inner.read().peer_addr().ok_or_else(|| {
+                Error::with_message(Errno::ENOTCONN, "the socket is not connected")
+            })?;
+
+        Ok(endpoint.into())
+    }
+
+    fn sendmsg(
+        &self,
+        reader: &mut dyn MultiRead,
+        message_header: MessageHeader,
+        flags: SendRecvFlags,
+    ) -> Result<usize> {
+        // TODO: Deal with flags
+        if !flags.is_all_supported() {
+            warn!("unsupported flags: {:?}", flags);
+        }
+
+        let MessageHeader {
+            addr,
+            control_messages,
+        } = message_header;
+
+        let endpoint = match addr {
+            Some(addr) => Some(self.options.read().ip.endpoint_from(addr)?),
+            None => None,
+        };
+
+        if !control_messages.is_empty() {
+            // TODO: Support sending control message
+            warn!("sending control message is not supported");
+        }
+
+        // TODO: Block if the send buffer is full
+        self.try_send(reader, endpoint.as_ref(), flags)
+    }
+
+    fn recvmsg(
+        &self,
+        writer: &mut dyn MultiWrite,
+        flags: SendRecvFlags,
+    ) -> Result<(usize, MessageHeader)> {
+        // TODO: Deal with flags
+        if !flags.is_all_supported() {
+            warn!("unsupported flags: {:?}", flags);
+        }
+
+        let (received_bytes, peer_addr) =
+            self.block_on_with_flags(IoEvents::IN, flags, || self.try_recv(writer, flags))?;
+
+        // TODO: Receive control message
+
+        let message_header = MessageHeader::new(Some(peer_addr), Vec::new());
+
+        Ok((received_bytes, message_header))
+    }
+
+    fn get_option(&self, option: &mut dyn SocketOption) -> Result<()> {
+        sock_option_mut!(match option {
+            socket_errors @ SocketError => {
+                // TODO: Support socket errors for raw sockets
+                socket_errors.set(None);
+                return Ok(());
+            }
+            icmp_filter @ IcmpFilter => {
+                self.check_icmp_filter_option()?;
+                icmp_filter.set(self.options.read().icmp_filter);
+                return Ok(());
+            }
+            _ => (),
+        });
+
+        let inner = self.inner.read();
+        let options = self.options.read();
+
+        // Deal with socket-level options
+        match options.socket.get_option(option, &*inner) {
+            Err(err) if err.error() == Errno::ENOPROTOOPT => (),
+            res => return res,
+        }
+
+        // Deal with IP-level options
+        options.ip.get_option(option)
+    }
+
+    fn set_option(&self, option: &dyn SocketOption) -> Result<()> {
+        sock_option_ref!(match option {
+            icmp_filter @ IcmpFilter => {
+                self.check_icmp_filter_option()?;
+                self.options.write().icmp_filter = *icmp_filter.get().unwrap();
+                self.sync_icmp_filter();
+                return Ok(());
+            }
+            _ => (),
+        });
+
+        let inner = self.inner.read();
+        let mut options = self.options.write();
+
+        // Deal with socket-level options
+        match options.socket.set_option(option, &*inner) {
+            Err(err) if err.error() == Errno::ENOPROTOOPT => {
+                // Deal with IP-level options
+                options.ip.set_option(option, &*inner)?;
+            }
+            Err(err) => return Err(err),
+            Ok(_) => (),
+        }
+
+        Ok(())
+    }
+
+    fn pseudo_path(&self) -> &Path {
+        &self.pseudo_path
+    }
+}
+
+impl RawSocket {
+    /// Checks whether `ICMP_FILTER` is available, which is only true for raw ICMP sockets.
+    fn check_icmp_filter_option(&self) -> Result<()> {
+        match self.socket_type {
+            RawSocketType::Raw(IpProtocol::Icmp) => Ok(()),
+            RawSocketType::Raw(_) => return_errno_with_message!(
+                Errno::EOPNOTSUPP,
+                "ICMP_FILTER is only available for raw ICMP sockets"
+            ),
+            RawSocketType::Ping => return_errno_with_message!(
+                Errno::ENOPROTOOPT,
+                "raw-level options are not available for ping sockets"
+            ),
+        }
+    }
+}
+
+impl GetSocketLevelOption for Inner<UnboundRaw, BoundRaw> {
+    fn is_listening(&self) -> bool {
+        false
+    }
+}
+
+impl SetSocketLevelOption for Inner<UnboundRaw, BoundRaw> {}
+
+impl SetIpLevelOption for Inner<UnboundRaw, BoundRaw> {
+    fn set_hdrincl(&self, _hdrincl: bool) -> Result<()> {
+        let is_ping = match self {
+            Inner::Unbound(unbound_raw) => unbound_raw.socket_type() == RawSocketType::Ping,
+            Inner::Bound(bound_raw) => bound_raw.is_ping(),
+        };
+        if is_ping {
+            return_errno_with_message!(
+                Errno::ENOPROTOOPT,
+                "IP_HDRINCL cannot be set on ping sockets"
+            );
+        }
+        Ok(())
+    }
+
+    fn set_v6only(&self, _v6only: bool) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{net::socket::options::macros::impl_socket_options, prelude::*, process::Gid};
+
+impl_socket_options!(
+    pub struct IcmpFilter(u32);
+);
+
+/// The range of the group IDs that are allowed to create ping sockets.
+///
+/// This corresponds to `net.ipv4.ping_group_range` in Linux. The range is empty if `start` is
+/// greater than `end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PingGroupRange {
+    pub start: Gid,
+    pub end: Gid,
+}
+
+/// The global ping group range, with the start in the high 32 bits and the end in the low 32 bits.
+///
+/// By default, the range is empty, so no groups are allowed to create ping sockets.
+static PING_GROUP_RANGE: AtomicU64 = AtomicU64::new(1 << 32);
+
+impl PingGroupRange {
+    /// The maximum group ID that can be specified in the range.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/net/ipv4/sysctl_net_ipv4.c#L38>
+    const MAX_GID: u32 = i32::MAX as u32;
+
+    /// Creates a range from the start and the end (both inclusive).
+    ///
+    /// Like Linux, a range whose end is less than its start is turned into the default empty
+    /// range.
+    pub fn new(start: u32, end: u32) -> Result<Self> {
+        if start > Self::MAX_GID || end > Self::MAX_GID {
+            return_errno_with_message!(Errno::EINVAL, "the group ID is out of range");
+        }
+
+        if end < start {
+            return Ok(Self {
+                start: Gid::new(1),
+                end: Gid::new(0),
+            });
+        }
+
+        Ok(Self {
+            start: Gid::new(start),
+            end: Gid::new(end),
+        })
+    }
+
+    /// Returns the current range.
+    pub fn get() -> Self {
+        let range = PING_GROUP_RANGE.load(Ordering::Relaxed);
+        Self {
+            start: Gid::new((range >> 32) as u32),
+            end: Gid::new(range as u32),
+        }
+    }
+
+    /// Sets the current range.
+    ///
+    /// The existing sockets are not affected.
+    pub fn set(self) {
+        let range = ((u32::from(self.start) as u64) << 32) | u32::from(self.end) as u64;
+        PING_GROUP_RANGE.store(range, Ordering::Relaxed);
+    }
+
+    /// Returns whether the group ID is in the range.
+    pub fn contains(&self, gid: Gid) -> bool {
+        self.start <= gid && gid <= self.end
+    }
+}
@@ -0,0 +1,126 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_bigtcp::wire::{IpAddress, IpEndpoint, Ipv4Address};
+
+use super::{RawSocketType, bound::BoundRaw};
+use crate::{
+    events::IoEvents,
+    net::{
+        iface::{Iface, RawIpSocket},
+        socket::{
+            ip::{
+                DatagramObserver,
+                common::{get_ephemeral_iface, get_iface_to_bind},
+            },
+            util::datagram_common,
+        },
+    },
+    prelude::*,
+    process::signal::Pollee,
+};
+
+pub(super) struct UnboundRaw {
+    socket_type: RawSocketType,
+    /// The identifier requested by binding a ping socket to the unspecified address.
+    ///
+    /// This is ignored by raw sockets.
+    ident: Option<u16>,
+}
+
+impl UnboundRaw {
+    pub(super) fn new(socket_type: RawSocketType) -> Self {
+        Self {
+            socket_type,
+            ident: None,
+        }
+    }
+
+    pub(super) fn socket_type(&self) -> RawSocketType {
+        self.socket_type
+    }
+
+    /// Binds the socket to the unspecified address.
+    ///
+    /// The socket is not bound to any iface until it sends packets or connects, so only the
+    /// identifier of a ping socket is recorded here.
+    //
+    // TODO: Receive packets from all ifaces if the socket is bound to the unspecified address.
+    pub(super) fn bind_unspecified(&mut self, port: u16) {
+        if port != 0 {
+            self.ident = Some(port);
+        }
+    }
+
+    fn new_socket(
+        &self,
+        iface: Arc<Iface>,
+        local_addr: Ipv4Address,
+        ident: Option<u16>,
+        pollee: &Pollee,
+    ) -> Result<RawIpSocket> {
+        let observer = DatagramObserver::new(pollee.clone());
+
+        let socket = match self.socket_type {
+            RawSocketType::Raw(protocol) => {
+                RawIpSocket::new_raw(iface, local_addr, protocol, observer)
+            }
+            RawSocketType::Ping => RawIpSocket::new_ping(iface, local_addr, ident, observer)?,
+        };
+
+        Ok(socket)
+    }
+}
+
+impl datagram_common::Unbound for UnboundRaw {
+    type Endpoint = IpEndpoint;
+    type BindOptions = ();
+
+    type Bound = BoundRaw;
+
+    fn bind(
+        &mut self,
+        endpoint: &Self::Endpoint,
+        pollee: &Pollee,
+        _options: Self::BindOptions,
+    ) -> Result<Self::Bound> {
+        let IpAddress::Ipv4(local_addr) = endpoint.addr else {
+            return_errno_with_message!(Errno::EAFNOSUPPORT, "only IPv4 addresses are supported");
+        };
+
+        let Some(iface) = get_iface_to_bind(&endpoint.addr) else {
+            return_errno_with_message!(
+                Errno::EADDRNOTAVAIL,
+                "the address is not available from the local machine"
+            );
+        };
+
+        let ident = if endpoint.port != 0 {
+            Some(endpoint.port)
+        } else {
+            self.ident
+        };
+        let socket = self.new_socket(iface, local_addr, ident, pollee)?;
+        Ok(BoundRaw::new(socket))
+    }
+
+    fn bind_ephemeral(
+        &mut self,
+        remote_endpoint: &Self::Endpoint,
+        pollee: &Pollee,
+        _options: Self::BindOptions,
+    ) -> Result<Self::Bound> {
+        if !matches!(remote_endpoint.addr, IpAddress::Ipv4(_)) {
+            return_errno_with_message!(Errno::EAFNOSUPPORT, "only IPv4 addresses are supported");
+        }
+
+        // The local address is left unspecified, so the source address of the outgoing packets
+        // will be the address of the iface.
+        let iface = get_ephemeral_iface(&remote_endpoint.addr);
+        let socket = self.new_socket(iface, Ipv4Address::UNSPECIFIED, self.ident, pollee)?;
+        Ok(BoundRaw::new(socket))
+    }
+
+    fn check_io_events(&self) -> IoEvents {
+        IoEvents::OUT
+    }
+}
@@ -6,7 +6,10 @@ use aster_bigtcp::{
     wire::IpEndpoint,
 };
 
-use super::observer::StreamObserver;
+use super::{
+    observer::StreamObserver,
+    tls::{TLS_RECORD_TYPE_DATA, TLS_ULP_NAME, TlsReceiver, TlsSender, TlsUlp},
+};
 use crate::{
     events::IoEvents,
     net::{
@@ -32,6 +35,8 @@ pub(super) struct ConnectedStream {
     /// connection is established asynchronously will succeed and any subsequent `connect()` will
     /// fail.
     is_new_connection: bool,
+    /// The TLS upper layer protocol, if it is installed with `TCP_ULP`.
+    tls: Option<TlsUlp>,
 }
 
 impl ConnectedStream {
@@ -44,6 +49,7 @@ impl ConnectedStream {
             tcp_conn,
             remote_endpoint,
             is_new_connection,
+            tls: None,
         }
     }
 
@@ -69,17 +75,108 @@ impl ConnectedStream {
         Ok(())
     }
 
+    /// Receives some data.
+    ///
+    /// If TLS records are received, the type of the records is also returned.
     pub(super) fn try_recv(
         &self,
         writer: &mut dyn MultiWrite,
-        _flags: SendRecvFlags,
-    ) -> Result<(usize, NeedIfacePoll)> {
-        let result = self.tcp_conn.recv(|socket_buffer| {
+        flags: SendRecvFlags,
+    ) -> Result<(usize, Option<u8>, NeedIfacePoll)> {
+        if let Some(tls) = self.tls.as_ref() {
+            if let Some(receiver) = tls.rx().lock().as_mut() {
+                return self.try_recv_tls(receiver, writer, flags);
+            }
+        }
+
+        let (recv_bytes, need_poll) = self.recv_with(|socket_buffer| {
             match writer.write(&mut VmReader::from(&*socket_buffer)) {
                 Ok(len) => (len, Ok(len)),
                 Err(e) => (0, Err(e)),
             }
-        });
+        })?;
+        Ok((recv_bytes, None, need_poll))
+    }
+
+    fn try_recv_tls(
+        &self,
+        receiver: &mut TlsReceiver,
+        writer: &mut dyn MultiWrite,
+        flags: SendRecvFlags,
+    ) -> Result<(usize, Option<u8>, NeedIfacePoll)> {
+        let is_peek = flags.contains(SendRecvFlags::MSG_PEEK);
+
+        let mut recv_bytes = 0;
+        let mut record_type = None;
+        let mut need_poll = false;
+
+        // Like Linux, records of different types are never returned together, and a record that
+        // does not contain application data is always returned alone.
+        loop {
+            if !receiver.has_record() {
+                match self.recv_tls_record(receiver, &mut need_poll) {
+                    Ok(()) => (),
+                    // The data received so far is returned first.
+                    Err(_) if recv_bytes > 0 => break,
+                    Err(err) => return Err(err),
+                }
+            }
+            // The connection is closed by the peer.
+            let Some(type_) = receiver.record_type() else {
+                break;
+            };
+
+            if record_type.is_some_and(|record_type| record_type != type_) {
+                break;
+            }
+            record_type = Some(type_);
+
+            recv_bytes += receiver.read_record(writer, is_peek)?;
+            if is_peek || type_ != TLS_RECORD_TYPE_DATA || receiver.has_record() {
+                break;
+            }
+        }
+
+        let need_poll = if need_poll {
+            NeedIfacePoll::TRUE
+        } else {
+            NeedIfacePoll::FALSE
+        };
+        Ok((recv_bytes, record_type, need_poll))
+    }
+
+    /// Receives and decrypts the next TLS record.
+    ///
+    /// This method returns without a record if the connection is closed by the peer.
+    fn recv_tls_record(&self, receiver: &mut TlsReceiver, need_poll: &mut bool) -> Result<()> {
+        loop {
+            let missing_len = receiver.missing_len()?;
+            if missing_len == 0 {
+                break;
+            }
+
+            let (recv_bytes, need_poll_now) = self.recv_with(|socket_buffer| {
+                let len = socket_buffer.len().min(missing_len);
+                receiver.push_raw(&socket_buffer[..len]);
+                (len, Ok(len))
+            })?;
+            *need_poll |= *need_poll_now;
+            if recv_bytes == 0 {
+                return Ok(());
+            }
+        }
+
+        receiver.open_record()
+    }
+
+    /// Receives some data with `f`, which consumes the data in the receive buffer.
+    ///
+    /// This method returns zero if the connection is closed by the peer.
+    fn recv_with(
+        &self,
+        f: impl FnOnce(&mut [u8]) -> (usize, Result<usize>),
+    ) -> Result<(usize, NeedIfacePoll)> {
+        let result = self.tcp_conn.recv(f);
 
         match result {
             Ok((Ok(0), need_poll)) => {
@@ -102,17 +199,87 @@ impl ConnectedStream {
         }
     }
 
+    /// Sends some data.
+    ///
+    /// If the TLS keys for sending are installed, the data is sent as TLS records of the specified
+    /// type, which defaults to application data.
     pub(super) fn try_send(
         &self,
         reader: &mut dyn MultiRead,
         _flags: SendRecvFlags,
+        record_type: Option<u8>,
     ) -> Result<(usize, NeedIfacePoll)> {
-        let result = self.tcp_conn.send(|socket_buffer| {
+        if let Some(tls) = self.tls.as_ref() {
+            if let Some(sender) = tls.tx().lock().as_mut() {
+                let record_type = record_type.unwrap_or(TLS_RECORD_TYPE_DATA);
+                return self.try_send_tls(sender, reader, record_type);
+            }
+        }
+
+        self.send_with(|socket_buffer| {
             match reader.read(&mut VmWriter::from(socket_buffer)) {
                 Ok(len) => (len, Ok(len)),
                 Err(e) => (0, Err(e)),
             }
-        });
+        })
+    }
+
+    fn try_send_tls(
+        &self,
+        sender: &mut TlsSender,
+        reader: &mut dyn MultiRead,
+        record_type: u8,
+    ) -> Result<(usize, NeedIfacePoll)> {
+        let mut sent_bytes = 0;
+        let mut need_poll = false;
+
+        while !reader.is_empty() {
+            // A record is sent only if it fits in the send buffer, so that records are never
+            // partially sent.
+            let free_len = self
+                .tcp_conn
+                .raw_with(|socket| socket.send_capacity() - socket.send_queue());
+            let payload_len = sender.max_payload_len(free_len).min(reader.sum_lens());
+            if payload_len == 0 {
+                break;
+            }
+
+            let mut payload = vec![0u8; payload_len];
+            reader.read(&mut VmWriter::from(payload.as_mut_slice()))?;
+            let record = sender.seal_record(record_type, &payload);
+
+            // The send buffer may wrap around, so the record may be written in multiple parts.
+            let mut written_len = 0;
+            while written_len < record.len() {
+                let (len, need_poll_now) = self.send_with(|socket_buffer| {
+                    let len = socket_buffer.len().min(record.len() - written_len);
+                    socket_buffer[..len].copy_from_slice(&record[written_len..written_len + len]);
+                    (len, Ok(len))
+                })?;
+                written_len += len;
+                need_poll |= *need_poll_now;
+            }
+            sent_bytes += payload_len;
+        }
+
+        if sent_bytes == 0 && !reader.is_empty() {
+            return_errno_with_message!(Errno::EAGAIN, "the send buffer is full");
+        }
+
+        let need_poll = if need_poll {
+            NeedIfacePoll::TRUE
+        } else {
+            NeedIfacePoll::FALSE
+        };
+        Ok((sent_bytes, need_poll))
+    }
+
+    /// Sends some data with `f`, which writes the data to the send buffer.
+    fn send_with(
+        &self,
+        f: impl FnOnce(&mut [u8]) -> (usize, Result<usize>),
+    ) -> Result<(usize, NeedIfacePoll)> {
+        let result = self.tcp_conn.send(f);
 
         match result {
             Ok((Ok(0), need_poll)) => {
@@ -141,10 +308,36 @@ impl ConnectedStream {
         self.remote_endpoint
     }
 
+    /// Returns the time when the latest data arrived, as the duration since the Unix epoch.
+    pub(super) fn recv_timestamp(&self) -> Option<core::time::Duration> {
+        self.tcp_conn.raw_with(|socket| socket.recv_timestamp())
+    }
+
     pub(super) fn iface(&self) -> &Arc<Iface> {
         self.tcp_conn.iface()
     }
 
+    /// Installs the upper layer protocol (ULP) with the name.
+    pub(super) fn set_ulp(&mut self, name: &str) -> Result<()> {
+        if self.tls.is_some() {
+            return_errno_with_message!(Errno::EEXIST, "the ULP is already installed");
+        }
+
+        debug_assert_eq!(name, TLS_ULP_NAME);
+        self.tls = Some(TlsUlp::new());
+        Ok(())
+    }
+
+    /// Returns the name of the installed upper layer protocol (ULP), if any.
+    pub(super) fn ulp_name(&self) -> Option<&'static str> {
+        self.tls.as_ref().map(|_| TLS_ULP_NAME)
+    }
+
+    /// Returns the TLS upper layer protocol, if it is installed.
+    pub(super) fn tls(&self) -> Option<&TlsUlp> {
+        self.tls.as_ref()
+    }
+
     pub(super) fn bound_port(&self) -> &BoundPort {
         self.tcp_conn.bound_port()
     }
@@ -163,6 +356,9 @@ impl ConnectedStream {
     }
 
     pub(super) fn check_io_events(&self) -> IoEvents {
+        let tls_receiver = self.tls.as_ref().map(|tls| tls.rx().lock());
+        let tls_receiver = tls_receiver.as_ref().and_then(|receiver| receiver.as_ref());
+
         self.tcp_conn.raw_with(|socket| {
             let is_receiving_closed = socket.is_recv_shut() || !socket.may_recv_new();
             let is_sending_closed = !socket.may_send();
@@ -173,6 +369,11 @@ impl ConnectedStream {
             // otherwise, check if the socket can receive.
             if is_receiving_closed {
                 events |= IoEvents::IN | IoEvents::RDHUP;
+            } else if let Some(receiver) = tls_receiver {
+                // With TLS, the data can be received only after the whole record arrives.
+                if receiver.is_readable(socket.recv_queue()) {
+                    events |= IoEvents::IN;
+                }
             } else if socket.can_recv() {
                 events |= IoEvents::IN;
             }
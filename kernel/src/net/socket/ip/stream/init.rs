@@ -13,7 +13,7 @@ use crate::{
     net::{
         iface::BoundPort,
         socket::{
-            ip::common::{bind_port, get_ephemeral_endpoint},
+            ip::common::{BindOptions, bind_port, get_ephemeral_endpoint},
             util::SocketAddr,
         },
     },
@@ -69,12 +69,12 @@ impl InitStream {
         }
     }
 
-    pub(super) fn bind(&mut self, endpoint: &IpEndpoint, can_reuse: bool) -> Result<()> {
+    pub(super) fn bind(&mut self, endpoint: &IpEndpoint, options: &BindOptions) -> Result<()> {
         if self.bound_port.is_some() {
             return_errno_with_message!(Errno::EINVAL, "the socket is already bound to an address");
         }
 
-        self.bound_port = Some(bind_port(endpoint, can_reuse)?);
+        self.bound_port = Some(bind_port(endpoint, options)?);
 
         Ok(())
     }
@@ -87,7 +87,7 @@ impl InitStream {
         self,
         remote_endpoint: &IpEndpoint,
         option: &RawTcpOption,
-        can_reuse: bool,
+        bind_options: &BindOptions,
         observer: StreamObserver,
     ) -> core::result::Result<ConnectingStream, (Error, Self)> {
         debug_assert!(
@@ -98,8 +98,9 @@ impl InitStream {
         let bound_port = if let Some(bound_port) = self.bound_port {
             bound_port
         } else {
-            let endpoint = get_ephemeral_endpoint(remote_endpoint);
-            match bind_port(&endpoint, can_reuse) {
+            let bound_port = get_ephemeral_endpoint(remote_endpoint, bind_options)
+                .and_then(|endpoint| bind_port(&endpoint, bind_options));
+            match bound_port {
                 Ok(bound_port) => bound_port,
                 Err(err) => return Err((err, self)),
             }
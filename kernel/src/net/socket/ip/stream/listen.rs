@@ -3,6 +3,7 @@
 use aster_bigtcp::{
     errors::tcp::ListenError,
     socket::{RawTcpOption, RawTcpSetOption},
+    time::Duration,
     wire::IpEndpoint,
 };
 
@@ -74,6 +75,10 @@ impl ListenStream {
         set_option(&self.tcp_listener)
     }
 
+    pub(super) fn set_defer_accept(&self, defer_accept: Option<Duration>) {
+        self.tcp_listener.set_defer_accept(defer_accept);
+    }
+
     pub(super) fn into_listener(self) -> TcpListener {
         self.tcp_listener
     }
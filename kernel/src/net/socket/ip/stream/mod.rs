@@ -4,6 +4,7 @@ use core::sync::atomic::{AtomicBool, Ordering};
 
 use aster_bigtcp::{
     socket::{NeedIfacePoll, RawTcpOption, RawTcpSetOption},
+    time::Duration,
     wire::IpEndpoint,
 };
 use connected::{ConnectedStream, close_and_linger};
@@ -12,15 +13,18 @@ use init::InitStream;
 use listen::ListenStream;
 use observer::StreamObserver;
 use options::{
-    Congestion, DeferAccept, Inq, KEEPALIVE_INTERVAL, KeepIdle, MaxSegment, NoDelay, SynCnt,
-    UserTimeout, WindowClamp,
+    Congestion, CongestionControl, DeferAccept, Inq, KeepCnt, KeepIdle, KeepIntvl, MaxSegment,
+    NoDelay, SynCnt, TlsRx, TlsTx, Ulp, UserTimeout, WindowClamp,
 };
 use ostd::sync::{PreemptDisabled, RwLockReadGuard, RwLockWriteGuard};
 use takeable::Takeable;
+use tls::TLS_ULP_NAME;
 use util::{Retrans, TcpOptionSet};
 
 use super::{
-    addr::UNSPECIFIED_LOCAL_ENDPOINT,
+    addr::IpFamily,
+    common::BindOptions,
+    err_queue::ErrorQueue,
     options::{IpOptionSet, SetIpLevelOption},
 };
 use crate::{
@@ -31,12 +35,13 @@ use crate::{
         socket::{
             Socket,
             options::{
-                Error as SocketError, SocketOption,
+                Error as SocketError, KeepAlive, SocketOption,
                 macros::{sock_option_mut, sock_option_ref},
             },
             private::SocketPrivate,
             util::{
-                MessageHeader, SendRecvFlags, SockShutdownCmd, SocketAddr,
+                ControlMessage, MessageHeader, SendRecvFlags, SockShutdownCmd, SocketAddr,
+                TimestampControlMessage,
                 options::{GetSocketLevelOption, SetSocketLevelOption, SocketOptionSet},
             },
         },
@@ -52,14 +57,18 @@ mod init;
 mod listen;
 pub(super) mod observer;
 pub mod options;
+mod tls;
 mod util;
 
+pub use tls::TlsControlMessage;
+
 pub struct StreamSocket {
     // Lock order: `state` first, `options` second
     // FIXME: We perform userspace reads/writes when holding the spin locks (e.g., this state lock
     // and other locks in `aster-bigtcp`), which will break the atomic mode.
     state: RwLock<Takeable<State>>,
     options: RwLock<OptionSet>,
+    err_queue: SpinLock<ErrorQueue>,
 
     is_nonblocking: AtomicBool,
     pollee: Pollee,
@@ -85,45 +94,102 @@ struct OptionSet {
 }
 
 impl OptionSet {
-    fn new() -> Self {
+    fn new(family: IpFamily) -> Self {
         let socket = SocketOptionSet::new_tcp();
-        let ip = IpOptionSet::new_tcp();
+        let ip = IpOptionSet::new_tcp(family);
         let tcp = TcpOptionSet::new();
         OptionSet { socket, ip, tcp }
     }
 
     fn raw(&self) -> RawTcpOption {
         RawTcpOption {
-            keep_alive: self.socket.keep_alive().then_some(KEEPALIVE_INTERVAL),
+            keep_alive: self.keep_alive_interval(),
+            timeout: self.timeout(),
             is_nagle_enabled: !self.tcp.no_delay(),
+            congestion_control: self.tcp.congestion().to_raw(),
+            defer_accept: self.defer_accept_timeout(),
+            hop_limit: self.ip.ttl().get(),
+        }
+    }
+
+    fn bind_options(&self) -> BindOptions {
+        BindOptions::new(&self.socket, &self.ip)
+    }
+
+    /// Returns the maximum time to defer accepting a connection, if `TCP_DEFER_ACCEPT` is set.
+    fn defer_accept_timeout(&self) -> Option<Duration> {
+        match self.tcp.defer_accept().to_secs() {
+            0 => None,
+            seconds => Some(Duration::from_secs(seconds as u64)),
+        }
+    }
+
+    /// Returns the idle time after which a keepalive probe is sent, if keepalive is enabled.
+    //
+    // TODO: The raw socket sends the probes at a fixed interval, so we cannot send the first probe
+    // after `TCP_KEEPIDLE` and the following ones every `TCP_KEEPINTVL`. A dead peer is still
+    // detected in time, since the timeout below accounts for all the probes.
+    fn keep_alive_interval(&self) -> Option<Duration> {
+        if !self.socket.keep_alive() {
+            return None;
+        }
+
+        Some(Duration::from_secs(self.tcp.keep_idle() as u64))
+    }
+
+    /// Returns the time after which the connection is aborted if the peer does not respond.
+    ///
+    /// Like Linux, `TCP_USER_TIMEOUT` overrides the timeout determined by the keepalive options.
+    fn timeout(&self) -> Option<Duration> {
+        let user_timeout = self.tcp.user_timeout();
+        if user_timeout != 0 {
+            return Some(Duration::from_millis(user_timeout as u64));
+        }
+
+        if !self.socket.keep_alive() {
+            return None;
         }
+
+        let keep_idle = self.tcp.keep_idle() as u64;
+        let keep_intvl = self.tcp.keep_intvl() as u64;
+        let keep_cnt = self.tcp.keep_cnt() as u64;
+        Some(Duration::from_secs(keep_idle + keep_intvl * keep_cnt))
     }
 }
 
 impl StreamSocket {
-    pub fn new(is_nonblocking: bool) -> Arc<Self> {
+    pub fn new(is_nonblocking: bool, family: IpFamily) -> Arc<Self> {
         let init_stream = InitStream::new();
         Arc::new(Self {
             state: RwLock::new(Takeable::new(State::Init(init_stream))),
-            options: RwLock::new(OptionSet::new()),
+            options: RwLock::new(OptionSet::new(family)),
+            err_queue: SpinLock::new(ErrorQueue::new()),
             is_nonblocking: AtomicBool::new(is_nonblocking),
             pollee: Pollee::new(),
             pseudo_path: SockFs::new_path(),
         })
     }
 
-    fn new_accepted(connected_stream: ConnectedStream) -> Arc<Self> {
+    fn new_accepted(connected_stream: ConnectedStream, ip_options: IpOptionSet) -> Arc<Self> {
         let options = connected_stream.raw_with(|raw_tcp_socket| {
-            let mut options = OptionSet::new();
+            let mut options = OptionSet::new(ip_options.family());
+            options.ip.set_v6only(ip_options.v6only());
 
-            if raw_tcp_socket.keep_alive().is_some() {
+            if let Some(interval) = raw_tcp_socket.keep_alive() {
                 options.socket.set_keep_alive(true);
+                options.tcp.set_keep_idle(interval.secs() as u32);
             }
 
             if !raw_tcp_socket.nagle_enabled() {
                 options.tcp.set_no_delay(true);
             }
 
+            if let Some(congestion) =
+                CongestionControl::from_raw(raw_tcp_socket.congestion_control())
+            {
+                options.tcp.set_congestion(congestion);
+            }
+
             // TODO: Update other options for a newly-accepted socket
 
             options
@@ -135,6 +201,7 @@ impl StreamSocket {
         Arc::new(Self {
             options: RwLock::new(options),
             state: RwLock::new(Takeable::new(State::Connected(connected_stream))),
+            err_queue: SpinLock::new(ErrorQueue::new()),
             is_nonblocking: AtomicBool::new(false),
             pollee,
             pseudo_path: SockFs::new_path(),
@@ -240,7 +307,7 @@ impl StreamSocket {
             let (target_state, iface_to_poll) = match init_stream.connect(
                 remote_endpoint,
                 &raw_option,
-                options.socket.reuse_addr(),
+                &options.bind_options(),
                 StreamObserver::new(self.pollee.clone()),
             ) {
                 Ok(connecting_stream) => {
@@ -301,10 +368,12 @@ impl StreamSocket {
             return_errno_with_message!(Errno::EINVAL, "the socket is not listening");
         };
 
+        let ip_options = self.options.read().ip;
         let accepted = listen_stream.try_accept().map(|connected_stream| {
             let remote_endpoint = connected_stream.remote_endpoint();
-            let accepted_socket = Self::new_accepted(connected_stream);
-            (accepted_socket as _, remote_endpoint.into())
+            let accepted_socket = Self::new_accepted(connected_stream, ip_options);
+            let remote_addr = ip_options.family().socket_addr_from(remote_endpoint);
+            (accepted_socket as _, remote_addr)
         });
         let iface_to_poll = listen_stream.iface().clone();
 
@@ -319,7 +388,7 @@ impl StreamSocket {
         &self,
         writer: &mut dyn MultiWrite,
         flags: SendRecvFlags,
-    ) -> Result<(usize, SocketAddr)> {
+    ) -> Result<(usize, Option<core::time::Duration>, Option<u8>)> {
         let state = self.read_updated_state();
 
         let connected_stream = match state.as_ref() {
@@ -327,7 +396,7 @@ impl StreamSocket {
             State::Init(init_stream) => {
                 let result = init_stream.try_recv();
                 self.pollee.invalidate();
-                return result;
+                return result.map(|(recv_bytes, _)| (recv_bytes, None, None));
             }
             State::Listen(_) => {
                 return_errno_with_message!(Errno::ENOTCONN, "the socket is not connected")
@@ -340,19 +409,30 @@ impl StreamSocket {
         let result = connected_stream.try_recv(writer, flags);
         self.pollee.invalidate();
 
-        let (recv_bytes, need_poll) = result?;
+        let (recv_bytes, tls_record_type, need_poll) = result?;
         let iface_to_poll = need_poll.then(|| connected_stream.iface().clone());
-        let remote_endpoint = connected_stream.remote_endpoint();
+        // Linux reports the arrival time of the last received segment. We do not keep the time
+        // for each segment, so we report the arrival time of the latest data instead.
+        let timestamp = if recv_bytes > 0 {
+            connected_stream.recv_timestamp()
+        } else {
+            None
+        };
 
         drop(state);
         if let Some(iface) = iface_to_poll {
             iface.poll();
         }
 
-        Ok((recv_bytes, remote_endpoint.into()))
+        Ok((recv_bytes, timestamp, tls_record_type))
     }
 
-    fn try_send(&self, reader: &mut dyn MultiRead, flags: SendRecvFlags) -> Result<usize> {
+    fn try_send(
+        &self,
+        reader: &mut dyn MultiRead,
+        flags: SendRecvFlags,
+        tls_record_type: Option<u8>,
+    ) -> Result<usize> {
         let state = self.read_updated_state();
 
         let connected_stream = match state.as_ref() {
@@ -372,7 +452,7 @@ impl StreamSocket {
             }
         };
 
-        let result = connected_stream.try_send(reader, flags);
+        let result = connected_stream.try_send(reader, flags, tls_record_type);
         self.pollee.invalidate();
 
         let (sent_bytes, need_poll) = result?;
@@ -386,15 +466,28 @@ impl StreamSocket {
         Ok(sent_bytes)
     }
 
+    fn try_recv_err(&self) -> Result<(usize, MessageHeader)> {
+        let options = self.options.read();
+        let result = self
+            .err_queue
+            .lock()
+            .try_recv(options.ip.family(), &options.socket);
+        self.pollee.invalidate();
+
+        result
+    }
+
     fn check_io_events(&self) -> IoEvents {
         let state = self.read_updated_state();
 
-        match state.as_ref() {
+        let events = match state.as_ref() {
             State::Init(init_stream) => init_stream.check_io_events(),
             State::Connecting(connecting_stream) => connecting_stream.check_io_events(),
             State::Listen(listen_stream) => listen_stream.check_io_events(),
             State::Connected(connected_stream) => connected_stream.check_io_events(),
-        }
+        };
+
+        events | self.err_queue.lock().check_io_events()
     }
 
     fn test_and_clear_error(&self) -> Option<Error> {
@@ -429,19 +522,19 @@ impl SocketPrivate for StreamSocket {
 
 impl Socket for StreamSocket {
     fn bind(&self, socket_addr: SocketAddr) -> Result<()> {
-        let endpoint = socket_addr.try_into()?;
+        let endpoint = self.options.read().ip.endpoint_from(socket_addr)?;
 
         let mut state = self.write_updated_state();
         let State::Init(init_stream) = state.as_mut() else {
             return_errno_with_message!(Errno::EINVAL, "the socket is already bound to an address");
         };
 
-        let can_reuse = self.options.read().socket.reuse_addr();
-        init_stream.bind(&endpoint, can_reuse)
+        let bind_options = self.options.read().bind_options();
+        init_stream.bind(&endpoint, &bind_options)
     }
 
     fn connect(&self, socket_addr: SocketAddr) -> Result<()> {
-        let remote_endpoint = socket_addr.try_into()?;
+        let remote_endpoint = self.options.read().ip.endpoint_from(socket_addr)?;
 
         if let Some(result) = self.start_connect(&remote_endpoint) {
             return result;
@@ -515,15 +608,16 @@ impl Socket for StreamSocket {
 
     fn addr(&self) -> Result<SocketAddr> {
         let state = self.read_updated_state();
+        let family = self.options.read().ip.family();
         let local_endpoint = match state.as_ref() {
             State::Init(init_stream) => init_stream
                 .local_endpoint()
-                .unwrap_or(UNSPECIFIED_LOCAL_ENDPOINT),
+                .unwrap_or(family.unspecified_local_endpoint()),
             State::Connecting(connecting_stream) => connecting_stream.local_endpoint(),
             State::Listen(listen_stream) => listen_stream.local_endpoint(),
             State::Connected(connected_stream) => connected_stream.local_endpoint(),
         };
-        Ok(local_endpoint.into())
+        Ok(family.socket_addr_from(local_endpoint))
     }
 
     fn peer_addr(&self) -> Result<SocketAddr> {
@@ -535,7 +629,8 @@ impl Socket for StreamSocket {
             State::Connecting(connecting_stream) => connecting_stream.remote_endpoint(),
             State::Connected(connected_stream) => connected_stream.remote_endpoint(),
         };
-        Ok(remote_endpoint.into())
+        let family = self.options.read().ip.family();
+        Ok(family.socket_addr_from(remote_endpoint))
     }
 
     fn sendmsg(
@@ -553,18 +648,58 @@ impl Socket for StreamSocket {
             control_messages, ..
         } = message_header;
 
+        // TCP Fast Open (TFO) is not supported because the handshake is done by the underlying
+        // TCP stack, which cannot carry data in SYN segments. So we behave like Linux when the
+        // client side of TFO is disabled.
+        if flags.contains(SendRecvFlags::MSG_FASTOPEN) {
+            return_errno_with_message!(Errno::EOPNOTSUPP, "TCP Fast Open is not supported");
+        }
+
         // According to the Linux man pages, `EISCONN` _may_ be returned when the destination
         // address is specified for a connection-mode socket. In practice, the destination address
         // is simply ignored. We follow the same behavior as the Linux implementation to ignore it.
 
-        if !control_messages.is_empty() {
-            // TODO: Support sending control message
-            warn!("sending control message is not supported");
+        let mut tls_record_type = None;
+        for control_message in control_messages {
+            match control_message {
+                ControlMessage::Tls(tls_control_message) => {
+                    tls_record_type = Some(tls_control_message.record_type());
+                }
+                // TODO: Support sending other control messages
+                _ => warn!("sending control message is not supported"),
+            }
         }
 
-        self.block_on(IoEvents::OUT, || self.try_send(reader, flags))
+        let (is_zerocopy, timestamping) = {
+            let options = self.options.read();
+            let is_zerocopy =
+                flags.contains(SendRecvFlags::MSG_ZEROCOPY) && options.socket.zerocopy();
+            (is_zerocopy, options.socket.timestamping())
+        };
+
+        let sent_bytes = self.block_on(IoEvents::OUT, || {
+            self.try_send(reader, flags, tls_record_type)
+        })?;
 
         // TODO: Trigger `SIGPIPE` if the error code is `EPIPE` and `MSG_NOSIGNAL` is not specified
+
+        // Like Linux, empty sends are neither assigned IDs for zero-copy transmission nor
+        // timestamped.
+        if sent_bytes > 0 {
+            let mut err_queue = self.err_queue.lock();
+            if is_zerocopy {
+                err_queue.complete_zerocopy_send();
+            }
+            // Each byte takes one key.
+            let has_tx_timestamps = err_queue.record_tx_timestamps(timestamping, sent_bytes as u32);
+            drop(err_queue);
+
+            if is_zerocopy || has_tx_timestamps {
+                self.pollee.notify(IoEvents::ERR);
+            }
+        }
+
+        Ok(sent_bytes)
     }
 
     fn recvmsg(
@@ -577,13 +712,29 @@ impl Socket for StreamSocket {
             warn!("unsupported flags: {:?}", flags);
         }
 
-        let (received_bytes, _) = self.block_on(IoEvents::IN, || self.try_recv(writer, flags))?;
+        if flags.contains(SendRecvFlags::MSG_ERRQUEUE) {
+            return self.try_recv_err();
+        }
+
+        let (received_bytes, timestamp, tls_record_type) =
+            self.block_on_with_flags(IoEvents::IN, flags, || self.try_recv(writer, flags))?;
 
-        // TODO: Receive control message
+        // TODO: Receive other control messages
+        let mut control_messages = match timestamp {
+            Some(timestamp) => {
+                TimestampControlMessage::new_all(&self.options.read().socket, timestamp, false)
+            }
+            None => Vec::new(),
+        };
+        // TODO: Linux fails with `EIO` if a record that does not contain application data is
+        // received without a control message buffer. We cannot detect this case here.
+        if let Some(record_type) = tls_record_type {
+            control_messages.push(ControlMessage::Tls(TlsControlMessage::new(record_type)));
+        }
 
         // According to <https://elixir.bootlin.com/linux/v6.0.9/source/net/ipv4/tcp.c#L2645>,
         // peer address is ignored for connected socket.
-        let message_header = MessageHeader::new(None, Vec::new());
+        let message_header = MessageHeader::new(None, control_messages);
 
         Ok((received_bytes, message_header))
     }
@@ -639,6 +790,14 @@ impl Socket for StreamSocket {
                 let keep_idle = options.tcp.keep_idle();
                 tcp_keep_idle.set(keep_idle);
             }
+            tcp_keep_intvl @ KeepIntvl => {
+                let keep_intvl = options.tcp.keep_intvl();
+                tcp_keep_intvl.set(keep_intvl);
+            }
+            tcp_keep_cnt @ KeepCnt => {
+                let keep_cnt = options.tcp.keep_cnt();
+                tcp_keep_cnt.set(keep_cnt);
+            }
             tcp_syn_cnt @ SynCnt => {
                 let syn_cnt = options.tcp.syn_cnt();
                 tcp_syn_cnt.set(syn_cnt);
@@ -664,6 +823,21 @@ impl Socket for StreamSocket {
                 let inq = options.tcp.receive_inq();
                 tcp_inq.set(inq);
             }
+            tcp_ulp @ Ulp => {
+                let name = match state.as_ref() {
+                    State::Connected(connected_stream) => connected_stream.ulp_name(),
+                    _ => None,
+                };
+                tcp_ulp.set(name.unwrap_or_default().to_string());
+            }
+            tls_tx @ TlsTx => {
+                let tls = tls_ulp_of(state.as_ref())?;
+                tls_tx.set(tls.tx_info()?);
+            }
+            tls_rx @ TlsRx => {
+                let tls = tls_ulp_of(state.as_ref())?;
+                tls_rx.set(tls.rx_info()?);
+            }
             _ => return_errno_with_message!(
                 Errno::ENOPROTOOPT,
                 "the socket option to get is unknown"
@@ -694,11 +868,24 @@ impl Socket for StreamSocket {
             Ok(need_iface_poll) => need_iface_poll,
         };
 
+        // The keepalive timer depends on both the socket-level and the TCP-level options, so it
+        // is updated after the option set is updated.
+        let need_iface_poll = if option.as_any().is::<KeepAlive>() {
+            state.set_raw_timers(&options)
+        } else {
+            need_iface_poll
+        };
+
         let iface_to_poll = need_iface_poll.then(|| state.iface().cloned()).flatten();
 
         drop(state);
         drop(options);
 
+        // The readiness depends on whether the received data is decrypted as TLS records.
+        if option.as_any().is::<TlsRx>() {
+            self.pollee.invalidate();
+        }
+
         if let Some(iface) = iface_to_poll {
             iface.poll();
         }
@@ -746,8 +933,35 @@ fn do_tcp_setsockopt(
                 return_errno_with_message!(Errno::EINVAL, "the keep idle time is out of bounds");
             }
             options.tcp.set_keep_idle(*keepidle);
+            return Ok(state.set_raw_timers(options));
+        }
+        tcp_keep_intvl @ KeepIntvl => {
+            const MIN_KEEP_INTVL: u32 = 1;
+            const MAX_KEEP_INTVL: u32 = 32767;
 
-            // TODO: Track when the socket becomes idle to actually support keep idle.
+            let keep_intvl = tcp_keep_intvl.get().unwrap();
+            if *keep_intvl < MIN_KEEP_INTVL || *keep_intvl > MAX_KEEP_INTVL {
+                return_errno_with_message!(
+                    Errno::EINVAL,
+                    "the keepalive interval is out of bounds"
+                );
+            }
+            options.tcp.set_keep_intvl(*keep_intvl);
+            return Ok(state.set_raw_timers(options));
+        }
+        tcp_keep_cnt @ KeepCnt => {
+            const MIN_KEEP_CNT: u32 = 1;
+            const MAX_KEEP_CNT: u32 = 127;
+
+            let keep_cnt = tcp_keep_cnt.get().unwrap();
+            if *keep_cnt < MIN_KEEP_CNT || *keep_cnt > MAX_KEEP_CNT {
+                return_errno_with_message!(
+                    Errno::EINVAL,
+                    "the keepalive probe count is out of bounds"
+                );
+            }
+            options.tcp.set_keep_cnt(*keep_cnt);
+            return Ok(state.set_raw_timers(options));
         }
         tcp_syn_cnt @ SynCnt => {
             const MAX_TCP_SYN_CNT: u8 = 127;
@@ -765,6 +979,9 @@ fn do_tcp_setsockopt(
             }
             let retrans = Retrans::from_secs(seconds);
             options.tcp.set_defer_accept(retrans);
+            if let State::Listen(listen_stream) = state {
+                listen_stream.set_defer_accept(options.defer_accept_timeout());
+            }
         }
         tcp_window_clamp @ WindowClamp => {
             let window_clamp = tcp_window_clamp.get().unwrap();
@@ -778,6 +995,9 @@ fn do_tcp_setsockopt(
         tcp_congestion @ Congestion => {
             let congestion = tcp_congestion.get().unwrap();
             options.tcp.set_congestion(*congestion);
+            state.set_raw_option(|raw_socket: &dyn RawTcpSetOption| {
+                raw_socket.set_congestion_control(congestion.to_raw())
+            });
         }
         tcp_user_timeout @ UserTimeout => {
             let user_timeout = tcp_user_timeout.get().unwrap();
@@ -785,11 +1005,30 @@ fn do_tcp_setsockopt(
                 return_errno_with_message!(Errno::EINVAL, "the user timeout cannot be negative");
             }
             options.tcp.set_user_timeout(*user_timeout);
+            return Ok(state.set_raw_timers(options));
         }
         tcp_inq @ Inq => {
             let inq = tcp_inq.get().unwrap();
             options.tcp.set_receive_inq(*inq);
         }
+        tcp_ulp @ Ulp => {
+            let name = tcp_ulp.get().unwrap();
+            if name != TLS_ULP_NAME {
+                return_errno_with_message!(Errno::ENOENT, "the ULP is not supported");
+            }
+            let State::Connected(connected_stream) = state else {
+                return_errno_with_message!(Errno::ENOTCONN, "the socket is not connected");
+            };
+            connected_stream.set_ulp(name)?;
+        }
+        tls_tx @ TlsTx => {
+            let info = tls_tx.get().unwrap();
+            tls_ulp_of(state)?.set_tx(info)?;
+        }
+        tls_rx @ TlsRx => {
+            let info = tls_rx.get().unwrap();
+            tls_ulp_of(state)?.set_rx(info)?;
+        }
         _ =>
             return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option to be set is unknown"),
     });
@@ -797,6 +1036,15 @@ fn do_tcp_setsockopt(
     Ok(NeedIfacePoll::FALSE)
 }
 
+/// Returns the TLS upper layer protocol, which must have been installed with `TCP_ULP`.
+fn tls_ulp_of(state: &State) -> Result<&tls::TlsUlp> {
+    let tls = match state {
+        State::Connected(connected_stream) => connected_stream.tls(),
+        _ => None,
+    };
+    tls.ok_or_else(|| Error::with_message(Errno::ENOPROTOOPT, "the TLS ULP is not installed"))
+}
+
 impl State {
     /// Calls `f` to set raw socket option.
     ///
@@ -813,6 +1061,23 @@ impl State {
         }
     }
 
+    /// Updates the keepalive timer and the timeout of the raw socket according to `options`.
+    fn set_raw_timers(&self, options: &OptionSet) -> NeedIfacePoll {
+        let keep_alive = options.keep_alive_interval();
+        let timeout = options.timeout();
+
+        let set_timers = |raw_socket: &dyn RawTcpSetOption| {
+            let need_poll_for_keep_alive = raw_socket.set_keep_alive(keep_alive);
+            let need_poll_for_timeout = raw_socket.set_timeout(timeout);
+            *need_poll_for_keep_alive || *need_poll_for_timeout
+        };
+
+        match self.set_raw_option(set_timers) {
+            Some(true) => NeedIfacePoll::TRUE,
+            Some(false) | None => NeedIfacePoll::FALSE,
+        }
+    }
+
     fn iface(&self) -> Option<&Arc<Iface>> {
         match self {
             State::Init(_) => None,
@@ -849,17 +1114,27 @@ impl SetSocketLevelOption for State {
         bound_port.set_can_reuse(reuse_addr);
     }
 
-    fn set_keep_alive(&self, keep_alive: bool) -> NeedIfacePoll {
-        let interval = if keep_alive {
-            Some(KEEPALIVE_INTERVAL)
-        } else {
-            None
+    fn set_reuse_port(&self, reuse_port: bool) {
+        let bound_port = match self {
+            State::Init(init_stream) => {
+                if let Some(bound_port) = init_stream.bound_port() {
+                    bound_port
+                } else {
+                    return;
+                }
+            }
+            State::Connecting(connecting_stream) => connecting_stream.bound_port(),
+            State::Connected(connected_stream) => connected_stream.bound_port(),
+            // The `SO_REUSEPORT` group of a listening socket is determined when it starts
+            // listening, so changing the option afterwards has no effect.
+            State::Listen(_) => return,
         };
 
-        let set_keepalive = |raw_socket: &dyn RawTcpSetOption| raw_socket.set_keep_alive(interval);
+        bound_port.set_can_reuse_port(reuse_port);
+    }
 
-        self.set_raw_option(set_keepalive)
-            .unwrap_or(NeedIfacePoll::FALSE)
+    fn set_zerocopy(&self, _zerocopy: bool) -> Result<()> {
+        Ok(())
     }
 }
 
@@ -870,6 +1145,22 @@ impl SetIpLevelOption for State {
             "IP_HDRINCL cannot be set on TCP sockets"
         );
     }
+
+    fn set_v6only(&self, _v6only: bool) -> Result<()> {
+        match self {
+            State::Init(init_stream) if init_stream.bound_port().is_none() => Ok(()),
+            _ => return_errno_with_message!(
+                Errno::EINVAL,
+                "IPV6_V6ONLY cannot be set on bound sockets"
+            ),
+        }
+    }
+
+    fn set_ttl(&self, ttl: u8) {
+        self.set_raw_option(|raw_socket: &dyn RawTcpSetOption| {
+            raw_socket.set_hop_limit(ttl)
+        });
+    }
 }
 
 impl Drop for StreamSocket {
@@ -1,29 +1,53 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use aster_bigtcp::socket::CongestionControl as RawCongestionControl;
+
+pub use super::tls::TlsCryptoInfo;
 use crate::{net::socket::options::macros::impl_socket_options, prelude::*};
 
 impl_socket_options!(
     pub struct NoDelay(bool);
     pub struct MaxSegment(u32);
     pub struct KeepIdle(u32);
+    pub struct KeepIntvl(u32);
+    pub struct KeepCnt(u32);
     pub struct SynCnt(u8);
     pub struct DeferAccept(u32);
     pub struct WindowClamp(u32);
     pub struct Congestion(CongestionControl);
     pub struct UserTimeout(u32);
     pub struct Inq(bool);
+    pub struct Ulp(String);
+    pub struct TlsTx(TlsCryptoInfo);
+    pub struct TlsRx(TlsCryptoInfo);
 );
 
-#[derive(Debug, Clone, Copy)]
+/// A TCP congestion control algorithm.
+///
+/// BBR is not listed because the underlying TCP stack does not provide it yet. Like Linux without
+/// the `tcp_bbr` module, selecting it fails with `ENOENT`.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
 pub enum CongestionControl {
-    Reno,
-    Cubic,
+    Reno = 0,
+    Cubic = 1,
 }
 
+/// The default congestion control algorithm for new sockets.
+///
+/// This can be changed via `/proc/sys/net/ipv4/tcp_congestion_control`. Like Linux, it defaults
+/// to CUBIC.
+static DEFAULT_CONGESTION_CONTROL: AtomicU8 = AtomicU8::new(CongestionControl::Cubic as u8);
+
 impl CongestionControl {
     const RENO: &'static str = "reno";
     const CUBIC: &'static str = "cubic";
 
+    /// All the available congestion control algorithms.
+    pub const ALL: [Self; 2] = [Self::Reno, Self::Cubic];
+
     pub fn new(name: &str) -> Result<Self> {
         let congestion = match name {
             Self::RENO => Self::Reno,
@@ -40,11 +64,31 @@ impl CongestionControl {
             Self::Cubic => Self::CUBIC,
         }
     }
-}
 
-/// The keepalive interval.
-///
-/// The linux value can be found at `/proc/sys/net/ipv4/tcp_keepalive_intvl`,
-/// which is by default 75 seconds for most Linux distributions.
-pub(super) const KEEPALIVE_INTERVAL: aster_bigtcp::time::Duration =
-    aster_bigtcp::time::Duration::from_secs(75);
+    /// Returns the default congestion control algorithm for new sockets.
+    pub fn default_for_new_sockets() -> Self {
+        Self::try_from(DEFAULT_CONGESTION_CONTROL.load(Ordering::Relaxed)).unwrap()
+    }
+
+    /// Sets the default congestion control algorithm for new sockets.
+    ///
+    /// The existing sockets are not affected.
+    pub fn set_default_for_new_sockets(self) {
+        DEFAULT_CONGESTION_CONTROL.store(self as u8, Ordering::Relaxed);
+    }
+
+    pub(super) fn to_raw(self) -> RawCongestionControl {
+        match self {
+            Self::Reno => RawCongestionControl::Reno,
+            Self::Cubic => RawCongestionControl::Cubic,
+        }
+    }
+
+    pub(super) fn from_raw(raw: RawCongestionControl) -> Option<Self> {
+        match raw {
+            RawCongestionControl::Reno => Some(Self::Reno),
+            RawCongestionControl::Cubic => Some(Self::Cubic),
+            RawCongestionControl::None => None,
+        }
+    }
+}
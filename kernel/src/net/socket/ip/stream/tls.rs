@@ -0,0 +1,581 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Kernel TLS (kTLS), which encrypts and decrypts TLS records on TCP connections.
+//!
+//! Userspace performs the TLS handshake, installs the `tls` upper layer protocol (ULP) with
+//! `TCP_ULP`, and then hands over the negotiated keys with the `TLS_TX` and `TLS_RX` options.
+//! After that, the data sent is split into TLS records and encrypted, and the TLS records
+//! received are decrypted, so userspace only deals with the plaintext.
+//!
+//! Only the AES-GCM cipher suites of TLS 1.2 and TLS 1.3 are supported.
+//!
+//! Reference: <https://docs.kernel.org/networking/tls.html>
+
+use crate::{
+    net::socket::util::CControlHeader,
+    prelude::*,
+    util::{
+        MultiWrite,
+        crypto::aes::{AES_GCM_NONCE_SIZE, AES_GCM_TAG_SIZE, Aes, AesGcm},
+        net::CSocketOptionLevel,
+    },
+};
+
+/// The name of the TLS upper layer protocol.
+pub(super) const TLS_ULP_NAME: &str = "tls";
+
+/// The record type of application data.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/net/tls.h#L69>.
+pub(super) const TLS_RECORD_TYPE_DATA: u8 = 23;
+
+/// The maximum size of the plaintext in a record.
+const TLS_MAX_PAYLOAD_SIZE: usize = 1 << 14;
+
+/// The size of the record header, which consists of the type, the version, and the length.
+const TLS_HEADER_SIZE: usize = 5;
+
+/// The version in the record header, which is always TLS 1.2 for compatibility.
+const TLS_RECORD_VERSION: [u8; 2] = [0x03, 0x03];
+
+const TLS_SALT_SIZE: usize = 4;
+const TLS_IV_SIZE: usize = 8;
+
+/// The state of the TLS upper layer protocol on a TCP connection.
+pub(super) struct TlsUlp {
+    tx: SpinLock<Option<TlsSender>>,
+    rx: SpinLock<Option<TlsReceiver>>,
+}
+
+impl TlsUlp {
+    pub(super) fn new() -> Self {
+        Self {
+            tx: SpinLock::new(None),
+            rx: SpinLock::new(None),
+        }
+    }
+
+    /// Returns the state for sending records, if the keys have been installed.
+    pub(super) fn tx(&self) -> &SpinLock<Option<TlsSender>> {
+        &self.tx
+    }
+
+    /// Returns the state for receiving records, if the keys have been installed.
+    pub(super) fn rx(&self) -> &SpinLock<Option<TlsReceiver>> {
+        &self.rx
+    }
+
+    /// Installs the keys for sending records.
+    pub(super) fn set_tx(&self, info: &TlsCryptoInfo) -> Result<()> {
+        let mut tx = self.tx.lock();
+        if tx.is_some() {
+            return_errno_with_message!(Errno::EBUSY, "the TX keys are already installed");
+        }
+        *tx = Some(TlsSender(RecordCrypto::new(info)));
+        Ok(())
+    }
+
+    /// Installs the keys for receiving records.
+    pub(super) fn set_rx(&self, info: &TlsCryptoInfo) -> Result<()> {
+        let mut rx = self.rx.lock();
+        if rx.is_some() {
+            return_errno_with_message!(Errno::EBUSY, "the RX keys are already installed");
+        }
+        *rx = Some(TlsReceiver {
+            crypto: RecordCrypto::new(info),
+            raw: Vec::new(),
+            record: None,
+        });
+        Ok(())
+    }
+
+    /// Returns the current keys for sending records.
+    pub(super) fn tx_info(&self) -> Result<TlsCryptoInfo> {
+        match self.tx.lock().as_ref() {
+            Some(tx) => Ok(tx.0.info),
+            None => return_errno_with_message!(Errno::EBUSY, "the TX keys are not installed"),
+        }
+    }
+
+    /// Returns the current keys for receiving records.
+    pub(super) fn rx_info(&self) -> Result<TlsCryptoInfo> {
+        match self.rx.lock().as_ref() {
+            Some(rx) => Ok(rx.crypto.info),
+            None => return_errno_with_message!(Errno::EBUSY, "the RX keys are not installed"),
+        }
+    }
+}
+
+/// The state for sending records.
+pub(super) struct TlsSender(RecordCrypto);
+
+impl TlsSender {
+    /// Returns the maximum size of the plaintext that can be sent in `space` bytes.
+    pub(super) fn max_payload_len(&self, space: usize) -> usize {
+        space
+            .saturating_sub(self.0.overhead())
+            .min(TLS_MAX_PAYLOAD_SIZE)
+    }
+
+    /// Encrypts the plaintext as a record of the type.
+    pub(super) fn seal_record(&mut self, record_type: u8, payload: &[u8]) -> Vec<u8> {
+        let crypto = &mut self.0;
+        debug_assert!(payload.len() <= TLS_MAX_PAYLOAD_SIZE);
+
+        let mut record = Vec::with_capacity(payload.len() + crypto.overhead());
+        let body_len = payload.len() + crypto.overhead() - TLS_HEADER_SIZE;
+        let outer_type = match crypto.info.version {
+            TlsVersion::Tls12 => record_type,
+            TlsVersion::Tls13 => TLS_RECORD_TYPE_DATA,
+        };
+        record.push(outer_type);
+        record.extend_from_slice(&TLS_RECORD_VERSION);
+        record.extend_from_slice(&(body_len as u16).to_be_bytes());
+
+        // TLS 1.2 sends the explicit part of the nonce, while TLS 1.3 hides the real record type
+        // in the encrypted part.
+        let aad = match crypto.info.version {
+            TlsVersion::Tls12 => {
+                record.extend_from_slice(&crypto.info.iv);
+                crypto.tls12_aad(record_type, payload.len())
+            }
+            TlsVersion::Tls13 => {
+                let mut aad = Vec::with_capacity(TLS_HEADER_SIZE);
+                aad.extend_from_slice(&record[..TLS_HEADER_SIZE]);
+                aad
+            }
+        };
+
+        let data_start = record.len();
+        record.extend_from_slice(payload);
+        if crypto.info.version == TlsVersion::Tls13 {
+            record.push(record_type);
+        }
+        let nonce = crypto.nonce(&crypto.info.iv);
+        let tag = crypto.aead.seal(&nonce, &aad, &mut record[data_start..]);
+        record.extend_from_slice(&tag);
+
+        crypto.advance();
+        record
+    }
+}
+
+/// The state for receiving records.
+pub(super) struct TlsReceiver {
+    crypto: RecordCrypto,
+    /// The bytes of the record that has not been completely received.
+    raw: Vec<u8>,
+    /// The decrypted record that has not been completely read.
+    record: Option<PlainRecord>,
+}
+
+struct PlainRecord {
+    type_: u8,
+    data: Vec<u8>,
+    offset: usize,
+}
+
+impl TlsReceiver {
+    /// Returns whether a decrypted record is ready to be read.
+    pub(super) fn has_record(&self) -> bool {
+        self.record.is_some()
+    }
+
+    /// Returns the number of bytes to receive before the next record is complete.
+    ///
+    /// If the record header is incomplete, only the number of bytes to complete the header is
+    /// returned, since the length of the record is unknown.
+    pub(super) fn missing_len(&self) -> Result<usize> {
+        let Some(header) = self.raw.first_chunk::<TLS_HEADER_SIZE>() else {
+            return Ok(TLS_HEADER_SIZE - self.raw.len());
+        };
+
+        if header[1..3] != TLS_RECORD_VERSION {
+            return_errno_with_message!(Errno::EINVAL, "the record version is invalid");
+        }
+        let body_len = u16::from_be_bytes([header[3], header[4]]) as usize;
+        let overhead = self.crypto.overhead() - TLS_HEADER_SIZE;
+        if body_len > TLS_MAX_PAYLOAD_SIZE + overhead {
+            return_errno_with_message!(Errno::EMSGSIZE, "the record is too long");
+        }
+        if body_len < overhead {
+            return_errno_with_message!(Errno::EBADMSG, "the record is too short");
+        }
+
+        Ok(TLS_HEADER_SIZE + body_len - self.raw.len())
+    }
+
+    /// Returns whether a record can be read once `queued_len` more bytes are received.
+    ///
+    /// Malformed records are also reported as readable, so that the errors can be reported by
+    /// reading.
+    pub(super) fn is_readable(&self, queued_len: usize) -> bool {
+        self.record.is_some() || !matches!(self.missing_len(), Ok(len) if len > queued_len)
+    }
+
+    /// Appends the received bytes to the incomplete record.
+    pub(super) fn push_raw(&mut self, bytes: &[u8]) {
+        self.raw.extend_from_slice(bytes);
+    }
+
+    /// Decrypts the completely received record.
+    ///
+    /// If the record cannot be decrypted, it is kept so that the connection stays broken, since
+    /// the following records cannot be decrypted either.
+    pub(super) fn open_record(&mut self) -> Result<()> {
+        debug_assert!(self.record.is_none());
+        debug_assert!(matches!(self.missing_len(), Ok(0)));
+
+        let crypto = &self.crypto;
+        let (header, body) = self.raw.split_at(TLS_HEADER_SIZE);
+        let (body, tag) = body.split_at(body.len() - AES_GCM_TAG_SIZE);
+        let tag: &[u8; AES_GCM_TAG_SIZE] = tag.try_into().unwrap();
+
+        let (nonce, aad, mut data) = match crypto.info.version {
+            TlsVersion::Tls12 => {
+                let (explicit_iv, ciphertext) = body.split_at(TLS_IV_SIZE);
+                let nonce = crypto.nonce(explicit_iv.try_into().unwrap());
+                let aad = crypto.tls12_aad(header[0], ciphertext.len());
+                (nonce, aad, ciphertext.to_vec())
+            }
+            TlsVersion::Tls13 => (crypto.nonce(&crypto.info.iv), header.to_vec(), body.to_vec()),
+        };
+        if !crypto.aead.open(&nonce, &aad, &mut data, tag) {
+            return_errno_with_message!(Errno::EBADMSG, "the record cannot be authenticated");
+        }
+
+        // TLS 1.3 records end with the real record type, optionally followed by zero padding.
+        let type_ = match crypto.info.version {
+            TlsVersion::Tls12 => header[0],
+            TlsVersion::Tls13 => {
+                let Some(type_len) = data.iter().rposition(|byte| *byte != 0) else {
+                    return_errno_with_message!(Errno::EBADMSG, "the record type is missing");
+                };
+                let type_ = data[type_len];
+                data.truncate(type_len);
+                type_
+            }
+        };
+
+        self.crypto.advance();
+        self.raw.clear();
+        self.record = Some(PlainRecord {
+            type_,
+            data,
+            offset: 0,
+        });
+        Ok(())
+    }
+
+    /// Returns the type of the decrypted record.
+    pub(super) fn record_type(&self) -> Option<u8> {
+        self.record.as_ref().map(|record| record.type_)
+    }
+
+    /// Reads the decrypted record to the writer.
+    ///
+    /// The record is not consumed if `is_peek` is true.
+    pub(super) fn read_record(
+        &mut self,
+        writer: &mut dyn MultiWrite,
+        is_peek: bool,
+    ) -> Result<usize> {
+        let Some(record) = self.record.as_mut() else {
+            return Ok(0);
+        };
+
+        let len = writer.write(&mut VmReader::from(&record.data[record.offset..]))?;
+        if is_peek {
+            return Ok(len);
+        }
+
+        record.offset += len;
+        if record.offset == record.data.len() {
+            self.record = None;
+        }
+        Ok(len)
+    }
+}
+
+/// The cipher and the sequence number for one direction.
+struct RecordCrypto {
+    info: TlsCryptoInfo,
+    aead: AesGcm,
+}
+
+impl RecordCrypto {
+    fn new(info: &TlsCryptoInfo) -> Self {
+        let cipher = match &info.key {
+            TlsCipherKey::AesGcm128(key) => Aes::new_128(key),
+            TlsCipherKey::AesGcm256(key) => Aes::new_256(key),
+        };
+
+        Self {
+            info: *info,
+            aead: AesGcm::new(cipher),
+        }
+    }
+
+    /// Returns the number of bytes that a record adds to the plaintext.
+    fn overhead(&self) -> usize {
+        match self.info.version {
+            TlsVersion::Tls12 => TLS_HEADER_SIZE + TLS_IV_SIZE + AES_GCM_TAG_SIZE,
+            TlsVersion::Tls13 => TLS_HEADER_SIZE + 1 + AES_GCM_TAG_SIZE,
+        }
+    }
+
+    /// Returns the nonce of the record with the IV.
+    ///
+    /// In TLS 1.2, the IV is sent along with the record. In TLS 1.3, the IV is fixed and the
+    /// sequence number is mixed into the nonce.
+    fn nonce(&self, iv: &[u8; TLS_IV_SIZE]) -> [u8; AES_GCM_NONCE_SIZE] {
+        let mut nonce = [0u8; AES_GCM_NONCE_SIZE];
+        nonce[..TLS_SALT_SIZE].copy_from_slice(&self.info.salt);
+        nonce[TLS_SALT_SIZE..].copy_from_slice(iv);
+
+        if self.info.version == TlsVersion::Tls13 {
+            let seq = self.info.rec_seq.to_be_bytes();
+            for (byte, seq_byte) in nonce[TLS_SALT_SIZE..].iter_mut().zip(seq) {
+                *byte ^= seq_byte;
+            }
+        }
+        nonce
+    }
+
+    /// Returns the additional data of a TLS 1.2 record.
+    fn tls12_aad(&self, record_type: u8, payload_len: usize) -> Vec<u8> {
+        let mut aad = Vec::with_capacity(13);
+        aad.extend_from_slice(&self.info.rec_seq.to_be_bytes());
+        aad.push(record_type);
+        aad.extend_from_slice(&TLS_RECORD_VERSION);
+        aad.extend_from_slice(&(payload_len as u16).to_be_bytes());
+        aad
+    }
+
+    /// Advances to the next record.
+    fn advance(&mut self) {
+        let info = &mut self.info;
+        info.rec_seq = info.rec_seq.wrapping_add(1);
+
+        // Like Linux, the explicit part of the nonce in TLS 1.2 is also incremented.
+        if info.version == TlsVersion::Tls12 {
+            let iv = u64::from_be_bytes(info.iv).wrapping_add(1);
+            info.iv = iv.to_be_bytes();
+        }
+    }
+}
+
+/// The TLS version.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/tls.h#L67>.
+#[repr(u16)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+enum TlsVersion {
+    Tls12 = 0x0303,
+    Tls13 = 0x0304,
+}
+
+/// The cipher and its key.
+#[derive(Clone, Copy)]
+enum TlsCipherKey {
+    AesGcm128([u8; 16]),
+    AesGcm256([u8; 32]),
+}
+
+impl Debug for TlsCipherKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        // The key is not printed.
+        match self {
+            Self::AesGcm128(_) => f.write_str("AesGcm128"),
+            Self::AesGcm256(_) => f.write_str("AesGcm256"),
+        }
+    }
+}
+
+/// The keys and the sequence number for one direction of a TLS connection.
+///
+/// This corresponds to the cipher-specific structures in Linux, such as
+/// `struct tls12_crypto_info_aes_gcm_128`, which begin with `struct tls_crypto_info`.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/tls.h#L119>.
+#[derive(Debug, Clone, Copy)]
+pub struct TlsCryptoInfo {
+    version: TlsVersion,
+    key: TlsCipherKey,
+    /// The implicit part of the nonce.
+    salt: [u8; TLS_SALT_SIZE],
+    /// The explicit part of the nonce.
+    iv: [u8; TLS_IV_SIZE],
+    /// The sequence number of the next record.
+    rec_seq: u64,
+}
+
+/// `struct tls_crypto_info` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CTlsCryptoInfo {
+    version: u16,
+    cipher_type: u16,
+}
+
+/// `struct tls12_crypto_info_aes_gcm_128` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CTlsCryptoInfoAesGcm128 {
+    info: CTlsCryptoInfo,
+    iv: [u8; TLS_IV_SIZE],
+    key: [u8; 16],
+    salt: [u8; TLS_SALT_SIZE],
+    rec_seq: [u8; 8],
+}
+
+/// `struct tls12_crypto_info_aes_gcm_256` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CTlsCryptoInfoAesGcm256 {
+    info: CTlsCryptoInfo,
+    iv: [u8; TLS_IV_SIZE],
+    key: [u8; 32],
+    salt: [u8; TLS_SALT_SIZE],
+    rec_seq: [u8; 8],
+}
+
+const TLS_CIPHER_AES_GCM_128: u16 = 51;
+const TLS_CIPHER_AES_GCM_256: u16 = 52;
+
+impl TlsCryptoInfo {
+    /// The size of the common header.
+    pub const HEADER_LEN: usize = size_of::<CTlsCryptoInfo>();
+
+    /// The maximum size of the supported structures.
+    pub const MAX_LEN: usize = size_of::<CTlsCryptoInfoAesGcm256>();
+
+    /// Parses the bytes of the cipher-specific structure.
+    ///
+    /// Like Linux, the length must match the size of the structure exactly.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let Some(header) = bytes.get(..Self::HEADER_LEN) else {
+            return_errno_with_message!(Errno::EINVAL, "the crypto info is too short");
+        };
+        let header = CTlsCryptoInfo::from_bytes(header);
+        let version = TlsVersion::try_from(header.version)
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the TLS version is not supported"))?;
+
+        let expected_len = match header.cipher_type {
+            TLS_CIPHER_AES_GCM_128 => size_of::<CTlsCryptoInfoAesGcm128>(),
+            TLS_CIPHER_AES_GCM_256 => size_of::<CTlsCryptoInfoAesGcm256>(),
+            _ => return_errno_with_message!(Errno::EINVAL, "the cipher is not supported"),
+        };
+        if bytes.len() != expected_len {
+            return_errno_with_message!(Errno::EINVAL, "the crypto info has an invalid length");
+        }
+
+        let (key, iv, salt, rec_seq) = match header.cipher_type {
+            TLS_CIPHER_AES_GCM_128 => {
+                let c_info = CTlsCryptoInfoAesGcm128::from_bytes(bytes);
+                let key = TlsCipherKey::AesGcm128(c_info.key);
+                (key, c_info.iv, c_info.salt, c_info.rec_seq)
+            }
+            _ => {
+                let c_info = CTlsCryptoInfoAesGcm256::from_bytes(bytes);
+                let key = TlsCipherKey::AesGcm256(c_info.key);
+                (key, c_info.iv, c_info.salt, c_info.rec_seq)
+            }
+        };
+
+        Ok(Self {
+            version,
+            key,
+            salt,
+            iv,
+            rec_seq: u64::from_be_bytes(rec_seq),
+        })
+    }
+
+    /// Returns the bytes of the cipher-specific structure.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let rec_seq = self.rec_seq.to_be_bytes();
+        match self.key {
+            TlsCipherKey::AesGcm128(key) => {
+                let c_info = CTlsCryptoInfoAesGcm128 {
+                    info: self.c_header(TLS_CIPHER_AES_GCM_128),
+                    iv: self.iv,
+                    key,
+                    salt: self.salt,
+                    rec_seq,
+                };
+                c_info.as_bytes().to_vec()
+            }
+            TlsCipherKey::AesGcm256(key) => {
+                let c_info = CTlsCryptoInfoAesGcm256 {
+                    info: self.c_header(TLS_CIPHER_AES_GCM_256),
+                    iv: self.iv,
+                    key,
+                    salt: self.salt,
+                    rec_seq,
+                };
+                c_info.as_bytes().to_vec()
+            }
+        }
+    }
+
+    fn c_header(&self, cipher_type: u16) -> CTlsCryptoInfo {
+        CTlsCryptoInfo {
+            version: self.version as u16,
+            cipher_type,
+        }
+    }
+}
+
+/// A control message that carries the type of a TLS record.
+///
+/// The type is specified with `TLS_SET_RECORD_TYPE` when sending records, and is reported with
+/// `TLS_GET_RECORD_TYPE` when receiving records.
+#[derive(Debug)]
+pub struct TlsControlMessage {
+    record_type: u8,
+}
+
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/tls.h#L46>.
+const TLS_SET_RECORD_TYPE: i32 = 1;
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/tls.h#L47>.
+const TLS_GET_RECORD_TYPE: i32 = 2;
+
+impl TlsControlMessage {
+    pub(super) fn new(record_type: u8) -> Self {
+        Self { record_type }
+    }
+
+    /// Returns the record type.
+    pub fn record_type(&self) -> u8 {
+        self.record_type
+    }
+
+    pub fn read_from(header: &CControlHeader, reader: &mut VmReader) -> Result<Option<Self>> {
+        debug_assert_eq!(header.level(), Some(CSocketOptionLevel::SOL_TLS));
+
+        if header.type_() != TLS_SET_RECORD_TYPE {
+            return_errno_with_message!(Errno::EINVAL, "the TLS control message is unsupported");
+        }
+        if header.payload_len() != size_of::<u8>() {
+            return_errno_with_message!(Errno::EINVAL, "the TLS record type is invalid");
+        }
+
+        let record_type = reader.read_val::<u8>()?;
+        Ok(Some(Self { record_type }))
+    }
+
+    pub fn write_to(&self, writer: &mut VmWriter) -> Result<CControlHeader> {
+        let payload_len = size_of::<u8>();
+        if CControlHeader::payload_len_from_total(writer.avail())? < payload_len {
+            return_errno_with_message!(Errno::EINVAL, "the control message buffer is too small");
+        }
+
+        let header =
+            CControlHeader::new(CSocketOptionLevel::SOL_TLS, TLS_GET_RECORD_TYPE, payload_len);
+        writer.write_val(&header)?;
+        writer.write_val(&self.record_type)?;
+
+        Ok(header)
+    }
+}
@@ -12,6 +12,8 @@ pub(super) struct TcpOptionSet {
     no_delay: bool,
     maxseg: u32,
     keep_idle: u32,
+    keep_intvl: u32,
+    keep_cnt: u32,
     syn_cnt: u8,
     defer_accept: Retrans,
     window_clamp: u32,
@@ -22,6 +24,15 @@ pub(super) struct TcpOptionSet {
 
 pub(super) const DEFAULT_MAXSEG: u32 = 536;
 pub(super) const DEFAULT_KEEP_IDLE: u32 = 7200;
+/// The default interval between keepalive probes, in seconds.
+///
+/// The Linux value can be found at `/proc/sys/net/ipv4/tcp_keepalive_intvl`,
+/// which is by default 75 seconds for most Linux distributions.
+pub(super) const DEFAULT_KEEP_INTVL: u32 = 75;
+/// The default number of unanswered keepalive probes before the connection is aborted.
+///
+/// The Linux value can be found at `/proc/sys/net/ipv4/tcp_keepalive_probes`.
+pub(super) const DEFAULT_KEEP_CNT: u32 = 9;
 pub(super) const DEFAULT_SYN_CNT: u8 = 6;
 pub(super) const DEFAULT_WINDOW_CLAMP: u32 = 0x8000_0000;
 
@@ -31,10 +42,12 @@ impl TcpOptionSet {
             no_delay: false,
             maxseg: DEFAULT_MAXSEG,
             keep_idle: DEFAULT_KEEP_IDLE,
+            keep_intvl: DEFAULT_KEEP_INTVL,
+            keep_cnt: DEFAULT_KEEP_CNT,
             syn_cnt: DEFAULT_SYN_CNT,
             defer_accept: Retrans(0),
             window_clamp: DEFAULT_WINDOW_CLAMP,
-            congestion: CongestionControl::Reno,
+            congestion: CongestionControl::default_for_new_sockets(),
             user_timeout: 0,
             receive_inq: false,
         }
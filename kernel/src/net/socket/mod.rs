@@ -7,22 +7,26 @@ use util::{MessageHeader, SendRecvFlags, SockShutdownCmd, SocketAddr};
 
 use crate::{
     fs::{
-        file::{CreationFlags, FileLike, StatusFlags, file_table::FdFlags},
+        file::{CreationFlags, FileLike, Mappable, StatusFlags, file_table::FdFlags},
         pseudofs::SockFs,
         vfs::path::Path,
     },
+    net::iface::iface_ioctl,
     prelude::*,
-    util::{MultiRead, MultiWrite},
+    util::{MultiRead, MultiWrite, ioctl::RawIoctl},
 };
 
+pub mod alg;
 pub mod ip;
 pub mod netlink;
 pub mod options;
+pub mod packet;
 pub mod unix;
 pub mod util;
 pub mod vsock;
 
 mod private {
+    use super::util::SendRecvFlags;
     use crate::{events::IoEvents, prelude::*, process::signal::Pollable};
 
     /// Common methods for sockets, but private to the network module.
@@ -54,6 +58,30 @@ mod private {
                 self.wait_events(events, None, try_op)
             }
         }
+
+        /// Blocks until some events occur to complete I/O operations, respecting the flags.
+        ///
+        /// This method is similar to [`Self::block_on`], except that it will also fail with
+        /// [`EAGAIN`] instead of blocking if `MSG_DONTWAIT` is specified in the flags.
+        ///
+        /// [`EAGAIN`]: crate::error::Errno::EAGAIN
+        #[track_caller]
+        fn block_on_with_flags<F, R>(
+            &self,
+            events: IoEvents,
+            flags: SendRecvFlags,
+            mut try_op: F,
+        ) -> Result<R>
+        where
+            Self: Sized,
+            F: FnMut() -> Result<R>,
+        {
+            if flags.contains(SendRecvFlags::MSG_DONTWAIT) {
+                try_op()
+            } else {
+                self.block_on(events, try_op)
+            }
+        }
     }
 }
 
@@ -125,6 +153,11 @@ pub trait Socket: private::SocketPrivate + Send + Sync {
         flags: SendRecvFlags,
     ) -> Result<(usize, MessageHeader)>;
 
+    /// Obtains the mappable object to map this socket into the user address space.
+    fn mappable(&self) -> Result<Mappable> {
+        return_errno_with_message!(Errno::ENODEV, "the socket is not mappable");
+    }
+
     /// Returns a reference to the pseudo path associated with this socket.
     fn pseudo_path(&self) -> &Path;
 }
@@ -169,10 +202,19 @@ impl<T: Socket + 'static> FileLike for T {
         Ok(())
     }
 
+    fn ioctl(&self, raw_ioctl: RawIoctl) -> Result<i32> {
+        // Like Linux, all sockets support the ioctl commands that manage the ifaces.
+        iface_ioctl(raw_ioctl)
+    }
+
     fn as_socket(&self) -> Option<&dyn Socket> {
         Some(self)
     }
 
+    fn mappable(&self) -> Result<Mappable> {
+        Socket::mappable(self)
+    }
+
     fn path(&self) -> &Path {
         self.pseudo_path()
     }
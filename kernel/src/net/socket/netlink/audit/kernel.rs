@@ -0,0 +1,223 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! This module defines the kernel socket,
+//! which is responsible for handling requests from user space
+//! and sending audit records to user space.
+
+use core::marker::PhantomData;
+
+use super::message::{AuditDataSegment, AuditMessage, AuditSegment};
+use crate::{
+    net::socket::netlink::{
+        GroupIdSet,
+        addr::PortNum,
+        message::{CMsgSegHdr, DoneSegment, ErrorSegment, ProtocolSegment, SegHdrCommonFlags},
+        table::{NetlinkAuditProtocol, SupportedNetlinkProtocol},
+    },
+    prelude::*,
+    process::{credentials::capabilities::CapSet, posix_thread::AsPosixThread},
+    security::audit::{self, AuditRule, CAuditStatus},
+};
+
+pub(super) struct NetlinkAuditKernelSocket {
+    _private: PhantomData<()>,
+}
+
+impl NetlinkAuditKernelSocket {
+    const fn new() -> Self {
+        Self {
+            _private: PhantomData,
+        }
+    }
+
+    pub(super) fn handle_request(&self, request: &AuditSegment, dst_port: PortNum) {
+        debug!("netlink audit request: {:?}", request);
+
+        let request_header = request.header();
+
+        let response_segments = match request {
+            AuditSegment::Data(request_segment) => handle_data_request(request_segment, dst_port),
+            _ => Err(Error::with_message(
+                Errno::EOPNOTSUPP,
+                "the netlink audit request is not supported",
+            )),
+        };
+
+        let segments = match response_segments {
+            Ok(segments) => segments,
+            Err(error) => {
+                // Errors are always reported, regardless of the `ACK` flag.
+                let err_segment = ErrorSegment::new_from_request(request_header, Some(error));
+                self.report_error(err_segment, dst_port);
+                return;
+            }
+        };
+
+        // Like Linux, each reply segment is sent in its own message.
+        for segment in segments {
+            let response = AuditMessage::new(vec![segment]);
+
+            debug!("netlink audit response: {:?}", response);
+
+            NetlinkAuditProtocol::unicast(dst_port, response).unwrap();
+        }
+
+        let flags = SegHdrCommonFlags::from_bits_truncate(request_header.flags);
+        if flags.contains(SegHdrCommonFlags::ACK) {
+            let ack_segment = ErrorSegment::new_from_request(request_header, None);
+            let response = AuditMessage::new(vec![AuditSegment::Error(ack_segment)]);
+            NetlinkAuditProtocol::unicast(dst_port, response).unwrap();
+        }
+    }
+
+    pub(super) fn report_error(&self, err_segment: ErrorSegment, dst_port: PortNum) {
+        let response = AuditMessage::new(vec![AuditSegment::Error(err_segment)]);
+
+        debug!("netlink audit error: {:?}", response);
+
+        NetlinkAuditProtocol::unicast(dst_port, response).unwrap();
+    }
+}
+
+/// The types of the requests.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/audit.h#L53>.
+const AUDIT_GET: u16 = 1000;
+const AUDIT_SET: u16 = 1001;
+const AUDIT_USER: u16 = 1005;
+const AUDIT_ADD_RULE: u16 = 1011;
+const AUDIT_DEL_RULE: u16 = 1012;
+const AUDIT_LIST_RULES: u16 = 1013;
+const AUDIT_FIRST_USER_MSG: u16 = 1100;
+const AUDIT_LAST_USER_MSG: u16 = 1199;
+const AUDIT_FIRST_USER_MSG2: u16 = 2100;
+const AUDIT_LAST_USER_MSG2: u16 = 2999;
+
+fn handle_data_request(request: &AuditDataSegment, port: PortNum) -> Result<Vec<AuditSegment>> {
+    let request_header = request.header();
+    let payload = request.payload();
+
+    match request_header.type_ {
+        AUDIT_GET => {
+            check_permission(CapSet::AUDIT_CONTROL)?;
+            let status = audit::status();
+            let reply = reply_segment(request_header, AUDIT_GET, status.as_bytes().to_vec());
+            Ok(vec![reply])
+        }
+        AUDIT_SET => {
+            check_permission(CapSet::AUDIT_CONTROL)?;
+            // Like Linux, older versions of the structure, which are shorter, are accepted. The
+            // missing fields are zero, so they are not in the mask.
+            let mut status = CAuditStatus::new_zeroed();
+            let len = payload.len().min(size_of::<CAuditStatus>());
+            status.as_mut_bytes()[..len].copy_from_slice(&payload[..len]);
+            audit::set_status(&status, port)?;
+            Ok(Vec::new())
+        }
+        AUDIT_ADD_RULE => {
+            check_permission(CapSet::AUDIT_CONTROL)?;
+            let (rule, is_prepend) = AuditRule::parse(payload)?;
+            audit::add_rule(rule, is_prepend)?;
+            Ok(Vec::new())
+        }
+        AUDIT_DEL_RULE => {
+            check_permission(CapSet::AUDIT_CONTROL)?;
+            let (rule, _) = AuditRule::parse(payload)?;
+            audit::del_rule(&rule)?;
+            Ok(Vec::new())
+        }
+        AUDIT_LIST_RULES => {
+            check_permission(CapSet::AUDIT_CONTROL)?;
+            let mut segments: Vec<_> = audit::list_rules()
+                .into_iter()
+                .map(|rule| reply_segment(request_header, AUDIT_LIST_RULES, rule))
+                .collect();
+            let done_segment = DoneSegment::new_from_request(request_header, None);
+            segments.push(AuditSegment::Done(done_segment));
+            for segment in segments.iter_mut() {
+                let header = segment.header_mut();
+                header.flags |= SegHdrCommonFlags::MULTI.bits();
+            }
+            Ok(segments)
+        }
+        AUDIT_USER
+        | AUDIT_FIRST_USER_MSG..=AUDIT_LAST_USER_MSG
+        | AUDIT_FIRST_USER_MSG2..=AUDIT_LAST_USER_MSG2 => {
+            check_permission(CapSet::AUDIT_WRITE)?;
+            audit::log_user_message(request_header.type_, payload);
+            Ok(Vec::new())
+        }
+        _ => return_errno_with_message!(
+            Errno::EINVAL,
+            "the netlink audit request type is invalid or not supported"
+        ),
+    }
+}
+
+/// Creates a segment in reply to the request.
+fn reply_segment(request_header: &CMsgSegHdr, type_: u16, payload: Vec<u8>) -> AuditSegment {
+    let header = CMsgSegHdr {
+        len: 0,
+        type_,
+        flags: SegHdrCommonFlags::empty().bits(),
+        seq: request_header.seq,
+        pid: request_header.pid,
+    };
+    AuditSegment::Data(AuditDataSegment::new(header, payload))
+}
+
+/// Checks whether the current thread has the capability.
+fn check_permission(cap: CapSet) -> Result<()> {
+    let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+    if !credentials.effective_capset().contains(cap) {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "the netlink audit request requires more capabilities"
+        );
+    }
+
+    Ok(())
+}
+
+/// The multicast group that receives all the audit records.
+///
+/// Joining the group requires the `CAP_AUDIT_READ` capability.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/audit.h#L447>.
+pub(super) const AUDIT_NLGRP_READLOG: GroupIdSet = GroupIdSet::new(0x1);
+
+/// Sends an audit record to the audit daemon and the audit multicast group.
+///
+/// The record is sent to the audit daemon if its netlink port `daemon_port` is specified. This
+/// method returns whether the record has been sent to the audit daemon. If the record cannot be
+/// sent because the port no longer exists, the caller should consider that the audit daemon has
+/// exited.
+pub fn send_audit_record(daemon_port: Option<u32>, type_: u16, payload: &str) -> bool {
+    let header = CMsgSegHdr {
+        len: 0,
+        type_,
+        flags: SegHdrCommonFlags::empty().bits(),
+        seq: 0,
+        pid: 0,
+    };
+    let segment = AuditDataSegment::new(header, payload.as_bytes().to_vec());
+    let message = AuditMessage::new(vec![AuditSegment::Data(segment)]);
+
+    NetlinkAuditProtocol::multicast(AUDIT_NLGRP_READLOG, message.clone()).unwrap();
+
+    let Some(port) = daemon_port else {
+        return false;
+    };
+    if !NetlinkAuditProtocol::socket_table().read().contains_port(port) {
+        return false;
+    }
+    NetlinkAuditProtocol::unicast(port, message).unwrap();
+    true
+}
+
+/// FIXME: NETLINK_AUDIT_KERNEL should be a per-network namespace socket
+static NETLINK_AUDIT_KERNEL: NetlinkAuditKernelSocket = NetlinkAuditKernelSocket::new();
+
+pub(super) fn get_netlink_audit_kernel() -> &'static NetlinkAuditKernelSocket {
+    &NETLINK_AUDIT_KERNEL
+}
@@ -0,0 +1,136 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Netlink message types for the audit protocol.
+//!
+//! Unlike other netlink protocols, the payloads of audit segments do not contain attributes. The
+//! payload is either a C structure (e.g., `audit_status`) or a text, depending on the segment
+//! type.
+
+use align_ext::AlignExt;
+
+use crate::{
+    net::socket::netlink::{
+        message::{
+            CMsgSegHdr, ContinueRead, DoneSegment, ErrorSegment, Message, NLMSG_ALIGN,
+            ProtocolSegment,
+        },
+        table::MulticastMessage,
+    },
+    prelude::*,
+    util::{MultiRead, MultiWrite},
+};
+
+/// An audit netlink message.
+pub(in crate::net::socket::netlink) type AuditMessage = Message<AuditSegment>;
+
+impl MulticastMessage for AuditMessage {}
+
+/// The audit netlink segment, which is the basic unit of an audit netlink message.
+#[derive(Debug, Clone)]
+pub enum AuditSegment {
+    /// A request, a reply, or a record, whose payload depends on the segment type.
+    Data(AuditDataSegment),
+    Done(DoneSegment),
+    Error(ErrorSegment),
+}
+
+impl ProtocolSegment for AuditSegment {
+    fn header(&self) -> &CMsgSegHdr {
+        match self {
+            AuditSegment::Data(data_segment) => data_segment.header(),
+            AuditSegment::Done(done_segment) => done_segment.header(),
+            AuditSegment::Error(error_segment) => error_segment.header(),
+        }
+    }
+
+    fn header_mut(&mut self) -> &mut CMsgSegHdr {
+        match self {
+            AuditSegment::Data(data_segment) => data_segment.header_mut(),
+            AuditSegment::Done(done_segment) => done_segment.header_mut(),
+            AuditSegment::Error(error_segment) => error_segment.header_mut(),
+        }
+    }
+
+    fn read_from(reader: &mut dyn MultiRead) -> Result<ContinueRead<Self, ErrorSegment>> {
+        let header = reader
+            .read_val_opt::<CMsgSegHdr>()?
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "the reader length is too small"))?;
+
+        let payload_len_with_padding = header.calc_payload_len_with_padding(reader)?;
+
+        // The segment types below `NLMSG_MIN_TYPE` are reserved for the standard netlink segments
+        // (e.g., `NLMSG_ERROR`), which are not requests.
+        if header.type_ < NLMSG_MIN_TYPE {
+            reader.skip_some(payload_len_with_padding);
+            let error = Error::with_message(Errno::EOPNOTSUPP, "the segment type is not supported");
+            return Ok(ContinueRead::SkippedErr(ErrorSegment::new_from_request(
+                &header,
+                Some(error),
+            )));
+        }
+
+        // The length has been validated by `calc_payload_len_with_padding`.
+        let payload_len = header.len as usize - size_of::<CMsgSegHdr>();
+        let mut payload = vec![0u8; payload_len];
+        reader.read(&mut VmWriter::from(payload.as_mut_slice()))?;
+        reader.skip_some(payload_len_with_padding - payload_len);
+
+        Ok(ContinueRead::Parsed(AuditSegment::Data(AuditDataSegment {
+            header,
+            payload,
+        })))
+    }
+
+    fn write_to(&self, writer: &mut dyn MultiWrite) -> Result<()> {
+        match self {
+            AuditSegment::Data(data_segment) => data_segment.write_to(writer)?,
+            AuditSegment::Done(done_segment) => done_segment.write_to(writer)?,
+            AuditSegment::Error(error_segment) => error_segment.write_to(writer)?,
+        }
+        Ok(())
+    }
+}
+
+/// The minimum type of the segments that are not standard netlink segments.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/netlink.h#L117>.
+const NLMSG_MIN_TYPE: u16 = 0x10;
+
+/// An audit segment with a raw payload.
+#[derive(Debug, Clone)]
+pub struct AuditDataSegment {
+    header: CMsgSegHdr,
+    payload: Vec<u8>,
+}
+
+impl AuditDataSegment {
+    /// Creates a segment with the header and the payload.
+    ///
+    /// The length in the header is set according to the payload.
+    pub(super) fn new(mut header: CMsgSegHdr, payload: Vec<u8>) -> Self {
+        header.len = (size_of::<CMsgSegHdr>() + payload.len()) as u32;
+        Self { header, payload }
+    }
+
+    pub(super) fn header(&self) -> &CMsgSegHdr {
+        &self.header
+    }
+
+    fn header_mut(&mut self) -> &mut CMsgSegHdr {
+        &mut self.header
+    }
+
+    pub(super) fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    fn write_to(&self, writer: &mut dyn MultiWrite) -> Result<()> {
+        writer.write_val_trunc(&self.header)?;
+        writer.write(&mut VmReader::from(self.payload.as_slice()))?;
+
+        let padding_len = self.payload.len().align_up(NLMSG_ALIGN) - self.payload.len();
+        writer.skip_some(padding_len);
+
+        Ok(())
+    }
+}
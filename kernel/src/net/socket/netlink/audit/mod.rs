@@ -0,0 +1,18 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Netlink Audit Socket.
+//!
+//! The audit daemon uses the audit netlink protocol to configure the audit subsystem and to
+//! receive the audit records. Other user-space programs can also send their own records via the
+//! protocol.
+
+pub use kernel::send_audit_record;
+pub(super) use message::AuditMessage;
+
+use crate::net::socket::netlink::{common::NetlinkSocket, table::NetlinkAuditProtocol};
+
+mod bound;
+mod kernel;
+mod message;
+
+pub type NetlinkAuditSocket = NetlinkSocket<NetlinkAuditProtocol>;
@@ -2,8 +2,9 @@
 
 use crate::{
     events::IoEvents,
-    net::socket::netlink::{
-        GroupIdSet, NetlinkSocketAddr, receiver::MessageQueue, table::BoundHandle,
+    net::socket::{
+        netlink::{GroupIdSet, NetlinkSocketAddr, receiver::MessageQueue, table::BoundHandle},
+        util::SocketFilter,
     },
     prelude::*,
 };
@@ -64,4 +65,8 @@ impl<Message: 'static> BoundNetlink<Message> {
     pub(super) fn drop_groups(&mut self, groups: GroupIdSet) {
         self.handle.drop_groups(groups);
     }
+
+    pub(super) fn set_filter(&mut self, filter: Option<SocketFilter>) -> Option<SocketFilter> {
+        self.receive_queue.lock().set_filter(filter)
+    }
 }
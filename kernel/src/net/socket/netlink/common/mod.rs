@@ -13,12 +13,12 @@ use crate::{
         Socket,
         netlink::{AddMembership, DropMembership, table::SupportedNetlinkProtocol},
         options::{
-            Error as SocketError, SocketOption,
+            AttachBpf, AttachFilter, DetachFilter, Error as SocketError, SocketOption,
             macros::{sock_option_mut, sock_option_ref},
         },
         private::SocketPrivate,
         util::{
-            MessageHeader, SendRecvFlags, SocketAddr,
+            MessageHeader, SendRecvFlags, SocketAddr, SocketFilter,
             datagram_common::{Bound, Inner, select_remote_and_bind},
             options::{GetSocketLevelOption, SetSocketLevelOption, SocketOptionSet},
         },
@@ -80,7 +80,7 @@ where
             || {
                 self.inner
                     .write()
-                    .bind_ephemeral(&NetlinkSocketAddr::new_unspecified(), &self.pollee)
+                    .bind_ephemeral(&NetlinkSocketAddr::new_unspecified(), &self.pollee, ())
             },
             |bound, remote_endpoint| bound.try_send(reader, remote_endpoint, flags),
         )?;
@@ -104,6 +104,19 @@ where
 
         Ok(recv_bytes)
     }
+
+    /// Attaches the socket filter, or detaches it if `filter` is `None`.
+    fn set_filter(&self, filter: Option<SocketFilter>) -> Result<()> {
+        self.options.read().socket.check_filter_unlocked()?;
+
+        let is_detach = filter.is_none();
+        let old_filter = self.inner.write().set_filter(filter);
+        if is_detach && old_filter.is_none() {
+            return_errno_with_message!(Errno::ENOENT, "no filter is attached");
+        }
+
+        Ok(())
+    }
 }
 
 impl<P: SupportedNetlinkProtocol> Socket for NetlinkSocket<P>
@@ -111,7 +124,8 @@ where
     BoundNetlink<P::Message>: Bound<Endpoint = NetlinkSocketAddr>,
 {
     fn bind(&self, socket_addr: SocketAddr) -> Result<()> {
-        let endpoint = socket_addr.try_into()?;
+        let endpoint: NetlinkSocketAddr = socket_addr.try_into()?;
+        P::check_groups(endpoint.groups())?;
 
         self.inner.write().bind(&endpoint, &self.pollee, ())
     }
@@ -119,7 +133,7 @@ where
     fn connect(&self, socket_addr: SocketAddr) -> Result<()> {
         let endpoint = socket_addr.try_into()?;
 
-        self.inner.write().connect(&endpoint, &self.pollee)
+        self.inner.write().connect(&endpoint, &self.pollee, ())
     }
 
     fn addr(&self) -> Result<SocketAddr> {
@@ -177,7 +191,8 @@ where
         writer: &mut dyn MultiWrite,
         flags: SendRecvFlags,
     ) -> Result<(usize, MessageHeader)> {
-        let (received_len, addr) = self.block_on(IoEvents::IN, || self.try_recv(writer, flags))?;
+        let (received_len, addr) =
+            self.block_on_with_flags(IoEvents::IN, flags, || self.try_recv(writer, flags))?;
 
         // TODO: Receive control message
 
@@ -206,6 +221,21 @@ where
     }
 
     fn set_option(&self, option: &dyn SocketOption) -> Result<()> {
+        sock_option_ref!(match option {
+            attach_filter @ AttachFilter => {
+                let filter = attach_filter.get().unwrap().clone();
+                return self.set_filter(Some(filter));
+            }
+            attach_bpf @ AttachBpf => {
+                let filter = SocketFilter::new_ebpf(attach_bpf.get().unwrap().clone())?;
+                return self.set_filter(Some(filter));
+            }
+            _detach_filter @ DetachFilter => {
+                return self.set_filter(None);
+            }
+            _ => (),
+        });
+
         let mut inner = self.inner.write();
 
         // Deal with socket-level options
@@ -276,6 +306,13 @@ impl<P: SupportedNetlinkProtocol> Inner<UnboundNetlink<P>, BoundNetlink<P::Messa
             Inner::Bound(bound_socket) => bound_socket.drop_groups(groups),
         }
     }
+
+    fn set_filter(&mut self, filter: Option<SocketFilter>) -> Option<SocketFilter> {
+        match self {
+            Inner::Unbound(unbound_socket) => unbound_socket.set_filter(filter),
+            Inner::Bound(bound_socket) => bound_socket.set_filter(filter),
+        }
+    }
 }
 
 fn do_netlink_setsockopt<P: SupportedNetlinkProtocol>(
@@ -284,8 +321,9 @@ fn do_netlink_setsockopt<P: SupportedNetlinkProtocol>(
 ) -> Result<()> {
     sock_option_ref!(match option {
         add_membership @ AddMembership => {
-            let groups = add_membership.get().unwrap();
-            inner.add_groups(GroupIdSet::new(*groups));
+            let groups = GroupIdSet::new(*add_membership.get().unwrap());
+            P::check_groups(groups)?;
+            inner.add_groups(groups);
         }
         drop_membership @ DropMembership => {
             let groups = drop_membership.get().unwrap();
@@ -9,7 +9,7 @@ use crate::{
             GroupIdSet, NetlinkSocketAddr, common::bound::BoundNetlink, receiver::MessageQueue,
             table::SupportedNetlinkProtocol,
         },
-        util::datagram_common,
+        util::{SocketFilter, datagram_common},
     },
     prelude::*,
     process::signal::Pollee,
@@ -17,6 +17,7 @@ use crate::{
 
 pub(super) struct UnboundNetlink<P: SupportedNetlinkProtocol> {
     groups: GroupIdSet,
+    filter: Option<SocketFilter>,
     phantom: PhantomData<BoundNetlink<P::Message>>,
 }
 
@@ -24,6 +25,7 @@ impl<P: SupportedNetlinkProtocol> UnboundNetlink<P> {
     pub(super) const fn new() -> Self {
         Self {
             groups: GroupIdSet::new_empty(),
+            filter: None,
             phantom: PhantomData,
         }
     }
@@ -39,6 +41,10 @@ impl<P: SupportedNetlinkProtocol> UnboundNetlink<P> {
     pub(super) fn drop_groups(&mut self, groups: GroupIdSet) {
         self.groups.drop_groups(groups);
     }
+
+    pub(super) fn set_filter(&mut self, filter: Option<SocketFilter>) -> Option<SocketFilter> {
+        core::mem::replace(&mut self.filter, filter)
+    }
 }
 
 impl<P: SupportedNetlinkProtocol> datagram_common::Unbound for UnboundNetlink<P> {
@@ -54,7 +60,7 @@ impl<P: SupportedNetlinkProtocol> datagram_common::Unbound for UnboundNetlink<P>
         _options: Self::BindOptions,
     ) -> Result<Self::Bound> {
         let (message_queue, message_receiver) =
-            MessageQueue::<P::Message>::new_pair(pollee.clone());
+            MessageQueue::<P::Message>::new_pair(pollee.clone(), self.filter.clone());
 
         let bound_handle = {
             let endpoint = {
@@ -72,9 +78,10 @@ impl<P: SupportedNetlinkProtocol> datagram_common::Unbound for UnboundNetlink<P>
         &mut self,
         _remote_endpoint: &Self::Endpoint,
         pollee: &Pollee,
+        _options: Self::BindOptions,
     ) -> Result<Self::Bound> {
         let (message_queue, message_receiver) =
-            MessageQueue::<P::Message>::new_pair(pollee.clone());
+            MessageQueue::<P::Message>::new_pair(pollee.clone(), self.filter.clone());
 
         let bound_handle = {
             let endpoint = {
@@ -0,0 +1,104 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Handle requests to the `nlctrl` family, which resolves the generic netlink families.
+//!
+//! Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/genetlink.h>.
+
+use super::{
+    FAMILIES, GenlFamily,
+    util::{find_attr, finish_response, response_header},
+};
+use crate::{
+    net::socket::netlink::{
+        generic::message::{FamilySegment, GenlAttr, GenlSegment, GenlSegmentBody},
+        message::{CMsgSegHdr, GetRequestFlags},
+    },
+    prelude::*,
+};
+
+pub(super) const FAMILY: GenlFamily = GenlFamily {
+    id: GENL_ID_CTRL,
+    name: "nlctrl",
+    version: 2,
+    max_attr: CTRL_ATTR_MAX,
+    handle_request,
+};
+
+const GENL_ID_CTRL: u16 = 0x10;
+
+const CTRL_CMD_NEWFAMILY: u8 = 1;
+const CTRL_CMD_GETFAMILY: u8 = 3;
+
+const CTRL_ATTR_FAMILY_ID: u16 = 1;
+const CTRL_ATTR_FAMILY_NAME: u16 = 2;
+const CTRL_ATTR_VERSION: u16 = 3;
+const CTRL_ATTR_HDRSIZE: u16 = 4;
+const CTRL_ATTR_MAXATTR: u16 = 5;
+const CTRL_ATTR_MAX: u32 = 10;
+
+fn handle_request(request_segment: &FamilySegment) -> Result<Vec<GenlSegment>> {
+    // TODO: Support other commands (e.g., `CTRL_CMD_GETPOLICY`).
+    if request_segment.body().cmd != CTRL_CMD_GETFAMILY {
+        return_errno_with_message!(Errno::EOPNOTSUPP, "the nlctrl command is not supported");
+    }
+
+    let dump_all = {
+        let flags = GetRequestFlags::from_bits_truncate(request_segment.header().flags);
+        flags.contains(GetRequestFlags::DUMP)
+    };
+
+    let families = if dump_all {
+        FAMILIES.iter().collect()
+    } else {
+        vec![find_family(request_segment.attrs())?]
+    };
+
+    let mut response_segments: Vec<GenlSegment> = families
+        .into_iter()
+        .map(|family| family_to_new_family(request_segment.header(), family))
+        .map(GenlSegment::Family)
+        .collect();
+
+    finish_response(request_segment.header(), dump_all, &mut response_segments);
+
+    Ok(response_segments)
+}
+
+/// Finds the family specified by the ID or the name in the request.
+fn find_family(attrs: &[GenlAttr]) -> Result<&'static GenlFamily> {
+    let family = if let Some(attr) = find_attr(attrs, CTRL_ATTR_FAMILY_ID) {
+        let id = attr.read_val::<u16>()?;
+        FAMILIES.iter().find(|family| family.id == id)
+    } else if let Some(attr) = find_attr(attrs, CTRL_ATTR_FAMILY_NAME) {
+        let name = attr.read_str()?;
+        FAMILIES.iter().find(|family| family.name == name)
+    } else {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "either the family ID or the family name should be specified"
+        );
+    };
+
+    family.ok_or_else(|| {
+        Error::with_message(Errno::ENOENT, "the generic netlink family does not exist")
+    })
+}
+
+fn family_to_new_family(request_header: &CMsgSegHdr, family: &GenlFamily) -> FamilySegment {
+    let header = response_header(request_header, GENL_ID_CTRL);
+
+    let body = GenlSegmentBody {
+        cmd: CTRL_CMD_NEWFAMILY,
+        version: FAMILY.version as u8,
+    };
+
+    let attrs = vec![
+        GenlAttr::new_str(CTRL_ATTR_FAMILY_NAME, family.name),
+        GenlAttr::new_val(CTRL_ATTR_FAMILY_ID, &family.id),
+        GenlAttr::new_val(CTRL_ATTR_VERSION, &family.version),
+        GenlAttr::new_val(CTRL_ATTR_HDRSIZE, &0u32),
+        GenlAttr::new_val(CTRL_ATTR_MAXATTR, &family.max_attr),
+    ];
+
+    FamilySegment::new(header, body, attrs)
+}
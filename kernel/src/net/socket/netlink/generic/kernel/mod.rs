@@ -0,0 +1,114 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! This module defines the kernel socket,
+//! which is responsible for handling requests from user space.
+
+use core::marker::PhantomData;
+
+use super::message::{FamilySegment, GenlMessage, GenlSegment};
+use crate::{
+    net::socket::netlink::{
+        addr::PortNum,
+        message::{ErrorSegment, ProtocolSegment, SegHdrCommonFlags},
+        table::{NetlinkGenericProtocol, SupportedNetlinkProtocol},
+    },
+    prelude::*,
+};
+
+mod ctrl;
+mod util;
+mod wireguard;
+
+pub(super) struct NetlinkGenericKernelSocket {
+    _private: PhantomData<()>,
+}
+
+impl NetlinkGenericKernelSocket {
+    const fn new() -> Self {
+        Self {
+            _private: PhantomData,
+        }
+    }
+
+    pub(super) fn handle_request(&self, request: &GenlSegment, dst_port: PortNum) {
+        debug!("netlink generic request: {:?}", request);
+
+        let request_header = request.header();
+
+        let response_segments = match request {
+            GenlSegment::Family(request_segment) => handle_family_request(request_segment),
+            _ => Err(Error::with_message(
+                Errno::EOPNOTSUPP,
+                "the netlink generic request is not supported",
+            )),
+        };
+
+        let mut segments = match response_segments {
+            Ok(segments) => segments,
+            Err(error) => {
+                // Errors are always reported, regardless of the `ACK` flag.
+                let err_segment = ErrorSegment::new_from_request(request_header, Some(error));
+                self.report_error(err_segment, dst_port);
+                return;
+            }
+        };
+
+        // Successful requests are acknowledged if the `ACK` flag is set, except for dump requests,
+        // whose responses end with done segments.
+        let flags = SegHdrCommonFlags::from_bits_truncate(request_header.flags);
+        if flags.contains(SegHdrCommonFlags::ACK)
+            && !matches!(segments.last(), Some(GenlSegment::Done(_)))
+        {
+            let ack_segment = ErrorSegment::new_from_request(request_header, None);
+            segments.push(GenlSegment::Error(ack_segment));
+        }
+        if segments.is_empty() {
+            return;
+        }
+
+        let response = GenlMessage::new(segments);
+
+        debug!("netlink generic response: {:?}", response);
+
+        NetlinkGenericProtocol::unicast(dst_port, response).unwrap();
+    }
+
+    pub(super) fn report_error(&self, err_segment: ErrorSegment, dst_port: PortNum) {
+        let response = GenlMessage::new(vec![GenlSegment::Error(err_segment)]);
+
+        debug!("netlink generic error: {:?}", response);
+
+        NetlinkGenericProtocol::unicast(dst_port, response).unwrap();
+    }
+}
+
+/// A generic netlink family implemented in the kernel.
+struct GenlFamily {
+    id: u16,
+    name: &'static str,
+    version: u32,
+    /// The maximum type of the top-level attributes.
+    max_attr: u32,
+    handle_request: fn(&FamilySegment) -> Result<Vec<GenlSegment>>,
+}
+
+/// The generic netlink families.
+///
+/// Unlike Linux, the families are registered statically, so their IDs are fixed.
+static FAMILIES: [GenlFamily; 2] = [ctrl::FAMILY, wireguard::FAMILY];
+
+fn handle_family_request(request: &FamilySegment) -> Result<Vec<GenlSegment>> {
+    let family_id = request.header().type_;
+    let Some(family) = FAMILIES.iter().find(|family| family.id == family_id) else {
+        return_errno_with_message!(Errno::ENOENT, "the generic netlink family does not exist");
+    };
+
+    (family.handle_request)(request)
+}
+
+/// FIXME: NETLINK_GENERIC_KERNEL should be a per-network namespace socket
+static NETLINK_GENERIC_KERNEL: NetlinkGenericKernelSocket = NetlinkGenericKernelSocket::new();
+
+pub(super) fn get_netlink_generic_kernel() -> &'static NetlinkGenericKernelSocket {
+    &NETLINK_GENERIC_KERNEL
+}
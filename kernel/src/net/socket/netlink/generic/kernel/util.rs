@@ -0,0 +1,61 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    net::socket::netlink::{
+        generic::message::{GenlAttr, GenlSegment},
+        message::{Attribute, CMsgSegHdr, DoneSegment, ProtocolSegment, SegHdrCommonFlags},
+    },
+    prelude::*,
+    process::{credentials::capabilities::CapSet, posix_thread::AsPosixThread},
+};
+
+/// Finishes a response message.
+pub(super) fn finish_response(
+    request_header: &CMsgSegHdr,
+    dump_all: bool,
+    response_segments: &mut Vec<GenlSegment>,
+) {
+    if !dump_all {
+        assert_eq!(response_segments.len(), 1);
+        return;
+    }
+
+    let done_segment = DoneSegment::new_from_request(request_header, None);
+    response_segments.push(GenlSegment::Done(done_segment));
+
+    for segment in response_segments.iter_mut() {
+        let header = segment.header_mut();
+        let mut flags = SegHdrCommonFlags::from_bits_truncate(header.flags);
+        flags |= SegHdrCommonFlags::MULTI;
+        header.flags = flags.bits();
+    }
+}
+
+/// Creates the header of a segment in response to the request.
+pub(super) fn response_header(request_header: &CMsgSegHdr, family_id: u16) -> CMsgSegHdr {
+    CMsgSegHdr {
+        len: 0,
+        type_: family_id,
+        flags: SegHdrCommonFlags::empty().bits(),
+        seq: request_header.seq,
+        pid: request_header.pid,
+    }
+}
+
+/// Finds the attribute with the type.
+pub(super) fn find_attr(attrs: &[GenlAttr], type_: u16) -> Option<&GenlAttr> {
+    attrs.iter().find(|attr| attr.type_() == type_)
+}
+
+/// Checks whether the current process has the `CAP_NET_ADMIN` capability.
+pub(super) fn check_permission() -> Result<()> {
+    let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+    if !credentials.effective_capset().contains(CapSet::NET_ADMIN) {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "the request requires the CAP_NET_ADMIN capability"
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,320 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Handle requests to the `wireguard` family, which configures the WireGuard ifaces.
+//!
+//! Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/wireguard.h>.
+
+use core::net::SocketAddrV4;
+
+use aster_bigtcp::wire::{Ipv4Address, Ipv4Cidr};
+
+use super::{
+    GenlFamily,
+    util::{check_permission, find_attr, finish_response, response_header},
+};
+use crate::{
+    net::{
+        iface::{Iface, iter_all_ifaces},
+        socket::netlink::{
+            generic::message::{FamilySegment, GenlAttr, GenlSegment, GenlSegmentBody},
+            message::{Attribute, CMsgSegHdr, GetRequestFlags},
+        },
+        wireguard::{DeviceConfig, DeviceUpdate, PeerConfig, PeerUpdate, get_config, set_config},
+    },
+    prelude::*,
+    time::timespec_t,
+    util::net::{CSocketAddrFamily, CSocketAddrInet},
+};
+
+pub(super) const FAMILY: GenlFamily = GenlFamily {
+    id: WG_GENL_ID,
+    name: "wireguard",
+    version: 1,
+    max_attr: WGDEVICE_A_PEERS as u32,
+    handle_request,
+};
+
+/// The ID of the family.
+///
+/// This is the first ID allocated dynamically in Linux.
+const WG_GENL_ID: u16 = 0x13;
+
+const WG_CMD_GET_DEVICE: u8 = 0;
+const WG_CMD_SET_DEVICE: u8 = 1;
+
+const WGDEVICE_A_IFINDEX: u16 = 1;
+const WGDEVICE_A_IFNAME: u16 = 2;
+const WGDEVICE_A_PRIVATE_KEY: u16 = 3;
+const WGDEVICE_A_PUBLIC_KEY: u16 = 4;
+const WGDEVICE_A_FLAGS: u16 = 5;
+const WGDEVICE_A_LISTEN_PORT: u16 = 6;
+const WGDEVICE_A_FWMARK: u16 = 7;
+const WGDEVICE_A_PEERS: u16 = 8;
+
+const WGDEVICE_F_REPLACE_PEERS: u32 = 1 << 0;
+
+const WGPEER_A_PUBLIC_KEY: u16 = 1;
+const WGPEER_A_PRESHARED_KEY: u16 = 2;
+const WGPEER_A_FLAGS: u16 = 3;
+const WGPEER_A_ENDPOINT: u16 = 4;
+const WGPEER_A_PERSISTENT_KEEPALIVE_INTERVAL: u16 = 5;
+const WGPEER_A_LAST_HANDSHAKE_TIME: u16 = 6;
+const WGPEER_A_RX_BYTES: u16 = 7;
+const WGPEER_A_TX_BYTES: u16 = 8;
+const WGPEER_A_ALLOWEDIPS: u16 = 9;
+const WGPEER_A_PROTOCOL_VERSION: u16 = 10;
+
+const WGPEER_F_REMOVE_ME: u32 = 1 << 0;
+const WGPEER_F_REPLACE_ALLOWEDIPS: u32 = 1 << 1;
+const WGPEER_F_UPDATE_ONLY: u32 = 1 << 2;
+
+const WGALLOWEDIP_A_FAMILY: u16 = 1;
+const WGALLOWEDIP_A_IPADDR: u16 = 2;
+const WGALLOWEDIP_A_CIDR_MASK: u16 = 3;
+
+const WG_KEY_LEN: usize = 32;
+
+/// The version of the WireGuard protocol.
+const PROTOCOL_VERSION: u32 = 1;
+
+fn handle_request(request_segment: &FamilySegment) -> Result<Vec<GenlSegment>> {
+    // Like Linux, all the commands require the `CAP_NET_ADMIN` capability, since the responses
+    // contain the private keys.
+    check_permission()?;
+
+    match request_segment.body().cmd {
+        WG_CMD_GET_DEVICE => do_get_device(request_segment),
+        WG_CMD_SET_DEVICE => do_set_device(request_segment),
+        _ => return_errno_with_message!(
+            Errno::EOPNOTSUPP,
+            "the WireGuard command is not supported"
+        ),
+    }
+}
+
+fn do_get_device(request_segment: &FamilySegment) -> Result<Vec<GenlSegment>> {
+    let flags = GetRequestFlags::from_bits_truncate(request_segment.header().flags);
+    if !flags.contains(GetRequestFlags::DUMP) {
+        return_errno_with_message!(Errno::EOPNOTSUPP, "the WireGuard device can only be dumped");
+    }
+
+    let iface = find_device(request_segment.attrs())?;
+    let config = get_config(iface.index())?;
+
+    let segment = device_to_segment(request_segment.header(), &iface, config);
+    let mut response_segments = vec![GenlSegment::Family(segment)];
+
+    // TODO: Split the response into multiple segments if there are too many peers.
+    finish_response(request_segment.header(), true, &mut response_segments);
+
+    Ok(response_segments)
+}
+
+fn do_set_device(request_segment: &FamilySegment) -> Result<Vec<GenlSegment>> {
+    let iface = find_device(request_segment.attrs())?;
+
+    let mut update = DeviceUpdate::default();
+    for attr in request_segment.attrs() {
+        match attr.type_() {
+            WGDEVICE_A_PRIVATE_KEY => update.private_key = Some(read_key(attr)?),
+            WGDEVICE_A_LISTEN_PORT => update.listen_port = Some(attr.read_val::<u16>()?),
+            WGDEVICE_A_FWMARK => update.fwmark = Some(attr.read_val::<u32>()?),
+            WGDEVICE_A_FLAGS => {
+                let flags = attr.read_val::<u32>()?;
+                if flags & !WGDEVICE_F_REPLACE_PEERS != 0 {
+                    return_errno_with_message!(Errno::EOPNOTSUPP, "the flags are not supported");
+                }
+                update.replace_peers = flags & WGDEVICE_F_REPLACE_PEERS != 0;
+            }
+            WGDEVICE_A_PEERS => {
+                for peer_attr in attr.read_nested()? {
+                    update.peers.push(parse_peer(&peer_attr)?);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    set_config(iface.index(), update)?;
+
+    Ok(Vec::new())
+}
+
+/// Finds the iface specified by exactly one of the index and the name in the request.
+fn find_device(attrs: &[GenlAttr]) -> Result<Arc<Iface>> {
+    let index = find_attr(attrs, WGDEVICE_A_IFINDEX)
+        .map(GenlAttr::read_val::<u32>)
+        .transpose()?;
+    let name = find_attr(attrs, WGDEVICE_A_IFNAME)
+        .map(GenlAttr::read_str)
+        .transpose()?;
+
+    let iface = match (index, name) {
+        (Some(index), None) => iter_all_ifaces().find(|iface| iface.index() == index),
+        (None, Some(name)) => iter_all_ifaces().find(|iface| iface.name() == name),
+        _ => return_errno_with_message!(
+            Errno::EBADR,
+            "exactly one of the interface index and the interface name should be specified"
+        ),
+    };
+
+    iface.ok_or_else(|| Error::with_message(Errno::ENODEV, "the interface does not exist"))
+}
+
+fn parse_peer(peer_attr: &GenlAttr) -> Result<PeerUpdate> {
+    let attrs = peer_attr.read_nested()?;
+
+    let Some(public_key) = find_attr(&attrs, WGPEER_A_PUBLIC_KEY) else {
+        return_errno_with_message!(Errno::EINVAL, "the public key of the peer is not specified");
+    };
+    let mut update = PeerUpdate::new(read_key(public_key)?);
+
+    for attr in attrs.iter() {
+        match attr.type_() {
+            WGPEER_A_PRESHARED_KEY => update.preshared_key = Some(read_key(attr)?),
+            WGPEER_A_FLAGS => {
+                let flags = attr.read_val::<u32>()?;
+                let supported_flags =
+                    WGPEER_F_REMOVE_ME | WGPEER_F_REPLACE_ALLOWEDIPS | WGPEER_F_UPDATE_ONLY;
+                if flags & !supported_flags != 0 {
+                    return_errno_with_message!(Errno::EOPNOTSUPP, "the flags are not supported");
+                }
+                update.remove = flags & WGPEER_F_REMOVE_ME != 0;
+                update.replace_allowed_ips = flags & WGPEER_F_REPLACE_ALLOWEDIPS != 0;
+                update.update_only = flags & WGPEER_F_UPDATE_ONLY != 0;
+            }
+            WGPEER_A_ENDPOINT => update.endpoint = Some(read_endpoint(attr)?),
+            WGPEER_A_PERSISTENT_KEEPALIVE_INTERVAL => {
+                update.persistent_keepalive_interval = Some(attr.read_val::<u16>()?);
+            }
+            WGPEER_A_ALLOWEDIPS => {
+                for allowed_ip_attr in attr.read_nested()? {
+                    update.allowed_ips.push(parse_allowed_ip(&allowed_ip_attr)?);
+                }
+            }
+            WGPEER_A_PROTOCOL_VERSION if attr.read_val::<u32>()? != PROTOCOL_VERSION => {
+                return_errno_with_message!(
+                    Errno::EPROTONOSUPPORT,
+                    "the protocol version is not supported"
+                );
+            }
+            _ => (),
+        }
+    }
+
+    Ok(update)
+}
+
+fn parse_allowed_ip(allowed_ip_attr: &GenlAttr) -> Result<Ipv4Cidr> {
+    let attrs = allowed_ip_attr.read_nested()?;
+
+    let (Some(family), Some(addr), Some(prefix_len)) = (
+        find_attr(&attrs, WGALLOWEDIP_A_FAMILY),
+        find_attr(&attrs, WGALLOWEDIP_A_IPADDR),
+        find_attr(&attrs, WGALLOWEDIP_A_CIDR_MASK),
+    ) else {
+        return_errno_with_message!(Errno::EINVAL, "the allowed IP is incomplete");
+    };
+
+    // TODO: Support IPv6 allowed IPs.
+    if family.read_val::<u16>()? != CSocketAddrFamily::AF_INET as u16 {
+        return_errno_with_message!(Errno::EAFNOSUPPORT, "only IPv4 allowed IPs are supported");
+    }
+    let addr = Ipv4Address::from(addr.read_val::<[u8; 4]>()?);
+    let prefix_len = prefix_len.read_val::<u8>()?;
+    if prefix_len > 32 {
+        return_errno_with_message!(Errno::EINVAL, "the prefix length is invalid");
+    }
+
+    Ok(Ipv4Cidr::new(addr, prefix_len))
+}
+
+fn read_key(attr: &GenlAttr) -> Result<[u8; WG_KEY_LEN]> {
+    attr.read_val::<[u8; WG_KEY_LEN]>()
+}
+
+fn read_endpoint(attr: &GenlAttr) -> Result<SocketAddrV4> {
+    // TODO: Support IPv6 endpoints.
+    let is_ipv4 = attr.payload().len() == size_of::<CSocketAddrInet>()
+        && attr.payload()[..2] == (CSocketAddrFamily::AF_INET as u16).to_ne_bytes();
+    if !is_ipv4 {
+        return_errno_with_message!(Errno::EAFNOSUPPORT, "only IPv4 endpoints are supported");
+    }
+
+    let (addr, port) = attr.read_val::<CSocketAddrInet>()?.into();
+    Ok(SocketAddrV4::new(addr, port))
+}
+
+fn device_to_segment(
+    request_header: &CMsgSegHdr,
+    iface: &Iface,
+    config: DeviceConfig,
+) -> FamilySegment {
+    let header = response_header(request_header, WG_GENL_ID);
+
+    let body = GenlSegmentBody {
+        cmd: WG_CMD_GET_DEVICE,
+        version: FAMILY.version as u8,
+    };
+
+    let mut attrs = vec![
+        GenlAttr::new_val(WGDEVICE_A_IFINDEX, &iface.index()),
+        GenlAttr::new_str(WGDEVICE_A_IFNAME, iface.name()),
+    ];
+    if let Some(private_key) = config.private_key.as_ref() {
+        attrs.push(GenlAttr::new(WGDEVICE_A_PRIVATE_KEY, private_key));
+    }
+    if let Some(public_key) = config.public_key.as_ref() {
+        attrs.push(GenlAttr::new(WGDEVICE_A_PUBLIC_KEY, public_key));
+    }
+    attrs.push(GenlAttr::new_val(WGDEVICE_A_LISTEN_PORT, &config.listen_port));
+    attrs.push(GenlAttr::new_val(WGDEVICE_A_FWMARK, &config.fwmark));
+
+    let peer_attrs = config
+        .peers
+        .iter()
+        .map(|peer| GenlAttr::new_nested(0, &peer_to_attrs(peer)))
+        .collect::<Vec<_>>();
+    attrs.push(GenlAttr::new_nested(WGDEVICE_A_PEERS, &peer_attrs));
+
+    FamilySegment::new(header, body, attrs)
+}
+
+fn peer_to_attrs(peer: &PeerConfig) -> Vec<GenlAttr> {
+    let mut attrs = vec![
+        GenlAttr::new(WGPEER_A_PUBLIC_KEY, &peer.public_key),
+        GenlAttr::new(WGPEER_A_PRESHARED_KEY, &peer.preshared_key),
+    ];
+    if let Some(endpoint) = peer.endpoint {
+        let addr = CSocketAddrInet::from((*endpoint.ip(), endpoint.port()));
+        attrs.push(GenlAttr::new_val(WGPEER_A_ENDPOINT, &addr));
+    }
+    attrs.push(GenlAttr::new_val(
+        WGPEER_A_PERSISTENT_KEEPALIVE_INTERVAL,
+        &peer.persistent_keepalive_interval,
+    ));
+    attrs.push(GenlAttr::new_val(
+        WGPEER_A_LAST_HANDSHAKE_TIME,
+        &timespec_t::from(peer.last_handshake_time),
+    ));
+    attrs.push(GenlAttr::new_val(WGPEER_A_RX_BYTES, &peer.rx_bytes));
+    attrs.push(GenlAttr::new_val(WGPEER_A_TX_BYTES, &peer.tx_bytes));
+
+    let allowed_ip_attrs = peer
+        .allowed_ips
+        .iter()
+        .map(|cidr| {
+            let attrs = [
+                GenlAttr::new_val(WGALLOWEDIP_A_FAMILY, &(CSocketAddrFamily::AF_INET as u16)),
+                GenlAttr::new(WGALLOWEDIP_A_IPADDR, &cidr.address().octets()),
+                GenlAttr::new_val(WGALLOWEDIP_A_CIDR_MASK, &cidr.prefix_len()),
+            ];
+            GenlAttr::new_nested(0, &attrs)
+        })
+        .collect::<Vec<_>>();
+    attrs.push(GenlAttr::new_nested(WGPEER_A_ALLOWEDIPS, &allowed_ip_attrs));
+
+    attrs.push(GenlAttr::new_val(WGPEER_A_PROTOCOL_VERSION, &PROTOCOL_VERSION));
+
+    attrs
+}
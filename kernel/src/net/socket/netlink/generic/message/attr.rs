@@ -0,0 +1,123 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    net::socket::netlink::message::{Attribute, CAttrHeader, ContinueRead},
+    prelude::*,
+    util::MultiRead,
+};
+
+/// A generic netlink attribute.
+///
+/// The meaning of an attribute depends on the generic netlink family and the command, so only
+/// the raw payload is kept here. The families parse the payloads with the helper methods.
+#[derive(Debug, Clone)]
+pub struct GenlAttr {
+    type_: u16,
+    payload: Vec<u8>,
+}
+
+impl GenlAttr {
+    /// Creates an attribute with the raw payload.
+    pub fn new(type_: u16, payload: &[u8]) -> Self {
+        Self {
+            type_,
+            payload: payload.to_vec(),
+        }
+    }
+
+    /// Creates an attribute whose payload is a plain value.
+    pub fn new_val<T: Pod>(type_: u16, val: &T) -> Self {
+        Self::new(type_, val.as_bytes())
+    }
+
+    /// Creates an attribute whose payload is a NUL-terminated string.
+    pub fn new_str(type_: u16, str: &str) -> Self {
+        let mut payload = Vec::with_capacity(str.len() + 1);
+        payload.extend_from_slice(str.as_bytes());
+        payload.push(0);
+
+        Self { type_, payload }
+    }
+
+    /// Creates an attribute whose payload consists of the nested attributes.
+    pub fn new_nested(type_: u16, attrs: &[GenlAttr]) -> Self {
+        let len = attrs.iter().map(GenlAttr::total_len_with_padding).sum();
+        let mut payload = vec![0u8; len];
+
+        let mut writer = VmWriter::from(payload.as_mut_slice()).to_fallible();
+        for attr in attrs {
+            // Writing to the kernel memory never fails.
+            attr.write_to(&mut writer).unwrap();
+        }
+
+        Self {
+            type_: type_ | NLA_F_NESTED,
+            payload,
+        }
+    }
+
+    /// Returns the raw payload.
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// Parses the payload as a plain value.
+    ///
+    /// This method fails with [`Errno::EINVAL`] if the payload length mismatches.
+    pub fn read_val<T: Pod>(&self) -> Result<T> {
+        if self.payload.len() != size_of::<T>() {
+            return_errno_with_message!(Errno::EINVAL, "the attribute length is invalid");
+        }
+
+        Ok(T::from_bytes(&self.payload))
+    }
+
+    /// Parses the payload as a string, which may or may not be NUL-terminated.
+    pub fn read_str(&self) -> Result<&str> {
+        let bytes = match self.payload.iter().position(|byte| *byte == 0) {
+            Some(nul_pos) => &self.payload[..nul_pos],
+            None => &self.payload,
+        };
+
+        core::str::from_utf8(bytes)
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the attribute is not a valid string"))
+    }
+
+    /// Parses the payload as nested attributes.
+    pub fn read_nested(&self) -> Result<Vec<GenlAttr>> {
+        let mut reader = VmReader::from(self.payload.as_slice()).to_fallible();
+        match GenlAttr::read_all_from(&mut reader, self.payload.len())? {
+            ContinueRead::Parsed(attrs) => Ok(attrs),
+            ContinueRead::Skipped => Ok(Vec::new()),
+            ContinueRead::SkippedErr(err) => Err(err),
+        }
+    }
+}
+
+impl Attribute for GenlAttr {
+    fn type_(&self) -> u16 {
+        self.type_
+    }
+
+    fn payload_as_bytes(&self) -> &[u8] {
+        &self.payload
+    }
+
+    fn read_from(header: &CAttrHeader, reader: &mut dyn MultiRead) -> Result<ContinueRead<Self>>
+    where
+        Self: Sized,
+    {
+        let mut payload = vec![0u8; header.payload_len()];
+        reader.read(&mut VmWriter::from(payload.as_mut_slice()))?;
+
+        Ok(ContinueRead::Parsed(Self {
+            type_: header.type_(),
+            payload,
+        }))
+    }
+}
+
+/// The flag that indicates the payload consists of nested attributes.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/netlink.h#L244>.
+const NLA_F_NESTED: u16 = 1 << 15;
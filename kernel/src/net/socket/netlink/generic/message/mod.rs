@@ -0,0 +1,19 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Netlink message types for the generic netlink protocol.
+//!
+//! This module defines how to interpret messages sent from user space and how to write
+//! kernel messages back to user space.
+
+mod attr;
+mod segment;
+
+pub(super) use attr::GenlAttr;
+pub(super) use segment::{FamilySegment, GenlSegment, GenlSegmentBody};
+
+use crate::net::socket::netlink::{message::Message, table::MulticastMessage};
+
+/// A generic netlink message.
+pub(in crate::net::socket::netlink) type GenlMessage = Message<GenlSegment>;
+
+impl MulticastMessage for GenlMessage {}
@@ -0,0 +1,117 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::attr::GenlAttr;
+use crate::{
+    net::socket::netlink::message::{
+        CMsgSegHdr, ContinueRead, DoneSegment, ErrorSegment, ProtocolSegment, SegmentBody,
+        SegmentCommon,
+    },
+    prelude::*,
+    util::{MultiRead, MultiWrite},
+};
+
+/// The generic netlink segment, which is the basic unit of a generic netlink message.
+#[derive(Debug, Clone)]
+pub enum GenlSegment {
+    /// A segment of a generic netlink family, whose ID is the segment type.
+    Family(FamilySegment),
+    Done(DoneSegment),
+    Error(ErrorSegment),
+}
+
+impl ProtocolSegment for GenlSegment {
+    fn header(&self) -> &CMsgSegHdr {
+        match self {
+            GenlSegment::Family(family_segment) => family_segment.header(),
+            GenlSegment::Done(done_segment) => done_segment.header(),
+            GenlSegment::Error(error_segment) => error_segment.header(),
+        }
+    }
+
+    fn header_mut(&mut self) -> &mut CMsgSegHdr {
+        match self {
+            GenlSegment::Family(family_segment) => family_segment.header_mut(),
+            GenlSegment::Done(done_segment) => done_segment.header_mut(),
+            GenlSegment::Error(error_segment) => error_segment.header_mut(),
+        }
+    }
+
+    fn read_from(reader: &mut dyn MultiRead) -> Result<ContinueRead<Self, ErrorSegment>> {
+        let header = reader
+            .read_val_opt::<CMsgSegHdr>()?
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "the reader length is too small"))?;
+
+        // The segment types below `GENL_MIN_ID` are reserved for the standard netlink segments
+        // (e.g., `NLMSG_ERROR`), which are not requests.
+        let segment = if header.type_ >= GENL_MIN_ID {
+            FamilySegment::read_from(&header, reader)?.map(GenlSegment::Family)
+        } else {
+            let payload_len = header.calc_payload_len_with_padding(reader)?;
+            reader.skip_some(payload_len);
+            ContinueRead::skipped_with_error(Errno::EOPNOTSUPP, "the segment type is not supported")
+        };
+
+        Ok(segment.map_err(|error| ErrorSegment::new_from_request(&header, Some(error))))
+    }
+
+    fn write_to(&self, writer: &mut dyn MultiWrite) -> Result<()> {
+        match self {
+            GenlSegment::Family(family_segment) => family_segment.write_to(writer)?,
+            GenlSegment::Done(done_segment) => done_segment.write_to(writer)?,
+            GenlSegment::Error(error_segment) => error_segment.write_to(writer)?,
+        }
+        Ok(())
+    }
+}
+
+/// The minimum ID of generic netlink families.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/netlink.h#L49>.
+const GENL_MIN_ID: u16 = 16;
+
+pub type FamilySegment = SegmentCommon<GenlSegmentBody, GenlAttr>;
+
+impl SegmentBody for GenlSegmentBody {
+    type CType = CGenlMsgHdr;
+}
+
+/// `genlmsghdr` in Linux.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/genetlink.h#L13>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub struct CGenlMsgHdr {
+    /// Command
+    pub cmd: u8,
+    /// Version of the family
+    pub version: u8,
+    /// Reserved bytes
+    pub reserved: u16,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GenlSegmentBody {
+    pub cmd: u8,
+    pub version: u8,
+}
+
+impl TryFrom<CGenlMsgHdr> for GenlSegmentBody {
+    type Error = Error;
+
+    fn try_from(value: CGenlMsgHdr) -> Result<Self> {
+        Ok(Self {
+            cmd: value.cmd,
+            version: value.version,
+        })
+    }
+}
+
+impl From<GenlSegmentBody> for CGenlMsgHdr {
+    fn from(value: GenlSegmentBody) -> Self {
+        CGenlMsgHdr {
+            cmd: value.cmd,
+            version: value.version,
+            reserved: 0,
+        }
+    }
+}
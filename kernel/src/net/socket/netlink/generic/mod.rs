@@ -0,0 +1,17 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Netlink Generic Socket.
+//!
+//! Generic netlink multiplexes multiple families over a single netlink protocol. Each family is
+//! identified by a numeric ID, which is used as the segment type. The IDs are resolved from the
+//! family names via the `nlctrl` family, whose ID is fixed.
+
+pub(super) use message::GenlMessage;
+
+use crate::net::socket::netlink::{common::NetlinkSocket, table::NetlinkGenericProtocol};
+
+mod bound;
+mod kernel;
+mod message;
+
+pub type NetlinkGenericSocket = NetlinkSocket<NetlinkGenericProtocol>;
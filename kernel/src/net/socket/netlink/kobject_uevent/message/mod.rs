@@ -1,12 +1,16 @@
 // SPDX-License-Identifier: MPL-2.0
 
-#![cfg_attr(not(ktest), expect(dead_code))]
+use core::str::FromStr;
 
+use syn_uevent::SyntheticUevent;
+pub use uevent::SysObjAction;
 use uevent::Uevent;
 
 use crate::{
     net::socket::netlink::{
-        NetlinkSocketAddr, receiver::QueueableMessage, table::MulticastMessage,
+        GroupIdSet, NetlinkSocketAddr,
+        receiver::QueueableMessage,
+        table::{MulticastMessage, NetlinkUeventProtocol, SupportedNetlinkProtocol},
     },
     prelude::*,
     util::MultiWrite,
@@ -54,6 +58,47 @@ impl QueueableMessage for UeventMessage {
     fn total_len(&self) -> usize {
         self.uevent.len()
     }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        self.uevent.as_bytes().to_vec()
+    }
 }
 
 impl MulticastMessage for UeventMessage {}
+
+/// The multicast group to which the kernel sends uevents.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.16/source/lib/kobject_uevent.c#L284>
+const KOBJECT_UEVENT_GROUP: GroupIdSet = GroupIdSet::new(0x1);
+
+/// Broadcasts a uevent of a `SysObj` at `devpath` under sysfs to the user space.
+pub fn broadcast_uevent(
+    action: SysObjAction,
+    devpath: String,
+    subsystem: String,
+    envs: Vec<(String, String)>,
+) -> Result<()> {
+    let uevent = Uevent::new(action, devpath, subsystem, envs);
+    broadcast(uevent)
+}
+
+/// Broadcasts a synthetic uevent of a `SysObj` at `devpath` under sysfs to the user space.
+///
+/// The synthetic uevent is triggered when `synth_uevent` is written to the `uevent` file of the
+/// `SysObj`.
+pub fn broadcast_synthetic_uevent(
+    synth_uevent: &str,
+    devpath: String,
+    subsystem: String,
+    envs: Vec<(String, String)>,
+) -> Result<()> {
+    let synth_uevent = SyntheticUevent::from_str(synth_uevent.trim_end_matches('\n'))?;
+    let uevent = Uevent::new_from_syn(synth_uevent, devpath, subsystem, envs);
+    broadcast(uevent)
+}
+
+fn broadcast(uevent: Uevent) -> Result<()> {
+    let src_addr = NetlinkSocketAddr::new(0, KOBJECT_UEVENT_GROUP);
+    let message = UeventMessage::new(uevent, src_addr);
+    NetlinkUeventProtocol::multicast(KOBJECT_UEVENT_GROUP, message)
+}
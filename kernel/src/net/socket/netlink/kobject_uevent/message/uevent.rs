@@ -14,7 +14,7 @@ use crate::prelude::*;
 /// Reference: <https://elixir.bootlin.com/linux/v6.14/source/include/linux/kobject.h#L53>.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
 #[repr(u8)]
-pub(super) enum SysObjAction {
+pub enum SysObjAction {
     /// Indicates the addition of a new `SysObj` to the system.
     ///
     /// Triggered when a device is discovered or registered.
@@ -97,7 +97,7 @@ pub(super) struct Uevent {
 
 impl Uevent {
     /// Creates a new uevent.
-    fn new(
+    pub(super) fn new(
         action: SysObjAction,
         devpath: String,
         subsystem: String,
@@ -1,6 +1,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
 pub(super) use message::UeventMessage;
+pub use message::{SysObjAction, broadcast_synthetic_uevent, broadcast_uevent};
 
 use crate::net::socket::netlink::{common::NetlinkSocket, table::NetlinkUeventProtocol};
 
@@ -15,7 +15,7 @@ pub(super) use segment::{
     CSegmentType, SegmentBody,
     ack::{DoneSegment, ErrorSegment},
     common::SegmentCommon,
-    header::{CMsgSegHdr, GetRequestFlags, SegHdrCommonFlags},
+    header::{CMsgSegHdr, GetRequestFlags, NewRequestFlags, SegHdrCommonFlags},
 };
 
 use super::receiver::QueueableMessage;
@@ -28,7 +28,7 @@ use crate::{
 ///
 /// A netlink message can be transmitted to and from user space using a single send/receive syscall.
 /// It consists of one or more [`ProtocolSegment`]s.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Message<T> {
     segments: Vec<T>,
 }
@@ -59,6 +59,14 @@ impl<T: ProtocolSegment> QueueableMessage for Message<T> {
             .map(|segment| segment.header().len as usize)
             .sum()
     }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0; self.total_len()];
+        let mut writer = VmWriter::from(bytes.as_mut_slice()).to_fallible();
+        // Writing to kernel memory never fails.
+        self.write_to(&mut writer).unwrap();
+        bytes
+    }
 }
 
 pub trait ProtocolSegment: Sized {
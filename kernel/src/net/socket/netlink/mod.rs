@@ -38,7 +38,9 @@
 //!
 
 mod addr;
+mod audit;
 mod common;
+mod generic;
 mod kobject_uevent;
 mod message;
 mod options;
@@ -47,7 +49,11 @@ mod route;
 mod table;
 
 pub use addr::{GroupIdSet, NetlinkSocketAddr};
-pub use kobject_uevent::NetlinkUeventSocket;
+pub use audit::{NetlinkAuditSocket, send_audit_record};
+pub use generic::NetlinkGenericSocket;
+pub use kobject_uevent::{
+    NetlinkUeventSocket, SysObjAction, broadcast_synthetic_uevent, broadcast_uevent,
+};
 pub use options::{AddMembership, DropMembership};
 pub(super) use receiver::NETLINK_DEFAULT_BUF_SIZE;
 pub use route::NetlinkRouteSocket;
@@ -1,6 +1,11 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use crate::{events::IoEvents, prelude::*, process::signal::Pollee};
+use crate::{
+    events::IoEvents,
+    net::socket::util::{FilterMetadata, SocketFilter},
+    prelude::*,
+    process::signal::Pollee,
+};
 
 pub struct MessageReceiver<Message> {
     message_queue: Arc<Mutex<MessageQueue<Message>>>,
@@ -11,15 +16,22 @@ pub(super) struct MessageQueue<Message> {
     messages: VecDeque<Message>,
     total_length: usize,
     error: Option<Error>,
+    filter: Option<SocketFilter>,
 }
 
 impl<Message> MessageQueue<Message> {
     /// Creates a pair of a [`MessageQueue`] and a [`MessageReceiver`].
-    pub(super) fn new_pair(pollee: Pollee) -> (Arc<Mutex<Self>>, MessageReceiver<Message>) {
+    ///
+    /// The messages that are rejected by the socket filter will not be enqueued.
+    pub(super) fn new_pair(
+        pollee: Pollee,
+        filter: Option<SocketFilter>,
+    ) -> (Arc<Mutex<Self>>, MessageReceiver<Message>) {
         let queue = Arc::new(Mutex::new(Self {
             messages: VecDeque::new(),
             total_length: 0,
             error: None,
+            filter,
         }));
         let receiver = MessageReceiver {
             message_queue: queue.clone(),
@@ -40,12 +52,20 @@ impl<Message> MessageQueue<Message> {
     pub(super) fn has_errors(&self) -> bool {
         self.error.is_some()
     }
+
+    /// Replaces the socket filter and returns the old one.
+    pub(super) fn set_filter(&mut self, filter: Option<SocketFilter>) -> Option<SocketFilter> {
+        core::mem::replace(&mut self.filter, filter)
+    }
 }
 
 /// Messages that fit into the [`MessageQueue`].
 pub trait QueueableMessage {
     /// Counts and returns the length of the message.
     fn total_len(&self) -> usize;
+
+    /// Returns the bytes of the message, which are what the socket filter runs on.
+    fn to_bytes(&self) -> Vec<u8>;
 }
 
 impl<Message: QueueableMessage> MessageQueue<Message> {
@@ -78,6 +98,24 @@ impl<Message: QueueableMessage> MessageQueue<Message> {
         Ok(result)
     }
 
+    /// Returns whether the socket filter accepts the message.
+    fn filter_accepts(&self, message: &Message) -> bool {
+        let Some(filter) = self.filter.as_ref() else {
+            return true;
+        };
+
+        // Netlink messages are not associated with any iface, so all the metadata is zero.
+        //
+        // TODO: Truncate the message if the filter returns a length smaller than the message.
+        let metadata = FilterMetadata {
+            protocol: 0,
+            pkttype: 0,
+            ifindex: 0,
+            hatype: 0,
+        };
+        filter.run_on_message(&message.to_bytes(), &metadata) != 0
+    }
+
     /// Tries to enqueue a new message. Returns `false` if the buffer is full.
     #[must_use]
     pub(self) fn enqueue(&mut self, message: Message) -> bool {
@@ -103,7 +141,13 @@ impl<Message: QueueableMessage> MessageQueue<Message> {
 
 impl<Message: QueueableMessage> MessageReceiver<Message> {
     pub(super) fn enqueue_message(&self, message: Message) {
-        let is_ok = self.message_queue.lock().enqueue(message);
+        let is_ok = {
+            let mut message_queue = self.message_queue.lock();
+            if !message_queue.filter_accepts(&message) {
+                return;
+            }
+            message_queue.enqueue(message)
+        };
         if is_ok {
             self.pollee.notify(IoEvents::IN);
         } else {
@@ -4,12 +4,16 @@
 
 use core::num::NonZeroU32;
 
-use super::util::finish_response;
+use aster_bigtcp::wire::{IpAddress, IpCidr};
+
+use super::util::{RtnlGroup, find_iface_by_index, finish_response, iface_addrs, notify};
 use crate::{
     net::{
         iface::{Iface, iter_all_ifaces},
         socket::netlink::{
-            message::{CMsgSegHdr, CSegmentType, GetRequestFlags, SegHdrCommonFlags},
+            message::{
+                CMsgSegHdr, CSegmentType, GetRequestFlags, NewRequestFlags, SegHdrCommonFlags,
+            },
             route::message::{
                 AddrAttr, AddrMessageFlags, AddrSegment, AddrSegmentBody, RtScope, RtnlSegment,
             },
@@ -28,9 +32,29 @@ pub(super) fn do_get_addr(request_segment: &AddrSegment) -> Result<Vec<RtnlSegme
         return_errno_with_message!(Errno::EOPNOTSUPP, "GETADDR only supports dump requests");
     }
 
+    // Only the addresses of the requested family are reported. Other families, including
+    // `AF_UNSPEC`, mean that all addresses should be reported.
+    let family = request_segment.body().family;
+    let is_family_matched = |ip_cidr: &IpCidr| match ip_cidr {
+        IpCidr::Ipv4(_) => family != CSocketAddrFamily::AF_INET6 as i32,
+        IpCidr::Ipv6(_) => family != CSocketAddrFamily::AF_INET as i32,
+    };
+
     let mut response_segments: Vec<RtnlSegment> = iter_all_ifaces()
         // GETADDR only supports dump mode, so we're going to report all addresses.
-        .filter_map(|iface| iface_to_new_addr(request_segment.header(), iface))
+        .flat_map(|iface| {
+            iface_addrs(&iface)
+                .into_iter()
+                .filter(is_family_matched)
+                .map(move |ip_cidr| {
+                    new_addr_segment(
+                        CSegmentType::NEWADDR,
+                        request_segment.header(),
+                        &iface,
+                        ip_cidr,
+                    )
+                })
+        })
         .map(RtnlSegment::NewAddr)
         .collect();
 
@@ -39,30 +63,174 @@ pub(super) fn do_get_addr(request_segment: &AddrSegment) -> Result<Vec<RtnlSegme
     Ok(response_segments)
 }
 
-fn iface_to_new_addr(request_header: &CMsgSegHdr, iface: &Arc<Iface>) -> Option<AddrSegment> {
-    let ipv4_addr = iface.ipv4_addr()?;
+pub(super) fn do_new_addr(request_segment: &AddrSegment) -> Result<Vec<RtnlSegment>> {
+    let (iface, ip_cidr) = parse_addr_request(request_segment)?;
+    let flags = NewRequestFlags::from_bits_truncate(request_segment.header().flags);
+
+    if iface.has_ip_addr(ip_cidr.address()) {
+        if flags.contains(NewRequestFlags::EXCL) || !flags.contains(NewRequestFlags::REPLACE) {
+            return_errno_with_message!(Errno::EEXIST, "the address already exists");
+        }
+        // Remove the old address because its prefix length may be different.
+        remove_addr(&iface, ip_cidr.address());
+    }
 
+    let is_added = match ip_cidr {
+        IpCidr::Ipv4(ipv4_cidr) => {
+            if iface.ipv4_addr().is_some() {
+                return_errno_with_message!(
+                    Errno::EOPNOTSUPP,
+                    "multiple IPv4 addresses on one interface are not supported"
+                );
+            }
+            iface.add_ipv4_addr(ipv4_cidr)
+        }
+        IpCidr::Ipv6(ipv6_cidr) => iface.add_ipv6_addr(ipv6_cidr),
+    };
+    if !is_added {
+        return_errno_with_message!(Errno::ENOSPC, "the interface cannot hold more addresses");
+    }
+
+    let segment = new_addr_segment(
+        CSegmentType::NEWADDR,
+        request_segment.header(),
+        &iface,
+        ip_cidr,
+    );
+    notify(addr_group(&ip_cidr), RtnlSegment::NewAddr(segment));
+
+    Ok(Vec::new())
+}
+
+pub(super) fn do_del_addr(request_segment: &AddrSegment) -> Result<Vec<RtnlSegment>> {
+    let (iface, ip_cidr) = parse_addr_request(request_segment)?;
+
+    // The prefix length in the request is ignored. The notification should report the prefix
+    // length of the removed address.
+    let Some(ip_cidr) = iface_addrs(&iface)
+        .into_iter()
+        .find(|iface_cidr| iface_cidr.address() == ip_cidr.address())
+    else {
+        return_errno_with_message!(Errno::EADDRNOTAVAIL, "the address does not exist");
+    };
+    if !remove_addr(&iface, ip_cidr.address()) {
+        return_errno_with_message!(Errno::EADDRNOTAVAIL, "the address does not exist");
+    }
+
+    let segment = new_addr_segment(
+        CSegmentType::DELADDR,
+        request_segment.header(),
+        &iface,
+        ip_cidr,
+    );
+    notify(addr_group(&ip_cidr), RtnlSegment::DelAddr(segment));
+
+    Ok(Vec::new())
+}
+
+/// Parses the iface and the address to add or delete.
+fn parse_addr_request(request_segment: &AddrSegment) -> Result<(Arc<Iface>, IpCidr)> {
+    let body = request_segment.body();
+
+    let Some(index) = body.index else {
+        return_errno_with_message!(Errno::ENODEV, "the interface is not specified");
+    };
+    let iface = find_iface_by_index(index.get())?;
+
+    // `IFA_LOCAL` is the address of the interface, while `IFA_ADDRESS` is the address of the
+    // other end for point-to-point interfaces. They are the same for other interfaces.
+    let find_local = |attr: &AddrAttr| match attr {
+        AddrAttr::Local(local) => Some(*local),
+        _ => None,
+    };
+    let find_address = |attr: &AddrAttr| match attr {
+        AddrAttr::Address(address) => Some(*address),
+        _ => None,
+    };
+    let attrs = request_segment.attrs();
+    let Some(ip_addr) = attrs
+        .iter()
+        .find_map(find_local)
+        .or_else(|| attrs.iter().find_map(find_address))
+    else {
+        return_errno_with_message!(Errno::EINVAL, "the address is not specified");
+    };
+    let ip_addr = IpAddress::from(ip_addr);
+
+    let (expected_family, max_prefix_len) = match ip_addr {
+        IpAddress::Ipv4(_) => (CSocketAddrFamily::AF_INET, 32),
+        IpAddress::Ipv6(_) => (CSocketAddrFamily::AF_INET6, 128),
+    };
+    if body.family != expected_family as i32 {
+        return_errno_with_message!(Errno::EINVAL, "the address does not match the family");
+    }
+    if body.prefix_len > max_prefix_len {
+        return_errno_with_message!(Errno::EINVAL, "the prefix length is invalid");
+    }
+
+    Ok((iface, IpCidr::new(ip_addr, body.prefix_len)))
+}
+
+fn remove_addr(iface: &Iface, ip_addr: IpAddress) -> bool {
+    match ip_addr {
+        IpAddress::Ipv4(ipv4_addr) => iface.remove_ipv4_addr(ipv4_addr),
+        IpAddress::Ipv6(ipv6_addr) => iface.remove_ipv6_addr(ipv6_addr),
+    }
+}
+
+fn addr_group(ip_cidr: &IpCidr) -> RtnlGroup {
+    match ip_cidr {
+        IpCidr::Ipv4(_) => RtnlGroup::IPV4_IFADDR,
+        IpCidr::Ipv6(_) => RtnlGroup::IPV6_IFADDR,
+    }
+}
+
+fn new_addr_segment(
+    type_: CSegmentType,
+    request_header: &CMsgSegHdr,
+    iface: &Iface,
+    ip_cidr: IpCidr,
+) -> AddrSegment {
     let header = CMsgSegHdr {
         len: 0,
-        type_: CSegmentType::NEWADDR as _,
+        type_: type_ as _,
         flags: SegHdrCommonFlags::empty().bits(),
         seq: request_header.seq,
         pid: request_header.pid,
     };
 
+    let (family, scope) = match ip_cidr.address() {
+        IpAddress::Ipv4(ipv4_addr) if ipv4_addr.is_loopback() => {
+            (CSocketAddrFamily::AF_INET, RtScope::HOST)
+        }
+        IpAddress::Ipv4(_) => (CSocketAddrFamily::AF_INET, RtScope::UNIVERSE),
+        IpAddress::Ipv6(ipv6_addr) if ipv6_addr.is_loopback() => {
+            (CSocketAddrFamily::AF_INET6, RtScope::HOST)
+        }
+        IpAddress::Ipv6(ipv6_addr) if ipv6_addr.is_unicast_link_local() => {
+            (CSocketAddrFamily::AF_INET6, RtScope::LINK)
+        }
+        IpAddress::Ipv6(_) => (CSocketAddrFamily::AF_INET6, RtScope::UNIVERSE),
+    };
+
     let addr_message = AddrSegmentBody {
-        family: CSocketAddrFamily::AF_INET as _,
-        prefix_len: iface.prefix_len().unwrap(),
+        family: family as _,
+        prefix_len: ip_cidr.prefix_len(),
         flags: AddrMessageFlags::PERMANENT,
-        scope: RtScope::HOST,
+        scope,
         index: NonZeroU32::new(iface.index()),
     };
 
-    let attrs = vec![
-        AddrAttr::Address(ipv4_addr.octets()),
-        AddrAttr::Label(CString::new(iface.name()).unwrap()),
-        AddrAttr::Local(ipv4_addr.octets()),
-    ];
+    let ip_addr = ip_cidr.address().into();
+    let attrs = match ip_cidr {
+        IpCidr::Ipv4(_) => vec![
+            AddrAttr::Address(ip_addr),
+            AddrAttr::Label(CString::new(iface.name()).unwrap()),
+            AddrAttr::Local(ip_addr),
+        ],
+        // Linux does not report the local addresses or the labels for IPv6 addresses.
+        IpCidr::Ipv6(_) => vec![AddrAttr::Address(ip_addr)],
+    };
 
-    Some(AddrSegment::new(header, addr_message, attrs))
+    AddrSegment::new(header, addr_message, attrs)
 }
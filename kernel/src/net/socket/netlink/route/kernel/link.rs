@@ -4,16 +4,23 @@
 
 use core::num::NonZero;
 
-use aster_bigtcp::iface::InterfaceType;
+use aster_bigtcp::iface::{InterfaceFlags, InterfaceType};
 
-use super::util::finish_response;
+use super::util::{find_iface_by_index, finish_response};
 use crate::{
     net::{
         iface::{Iface, iter_all_ifaces},
+        link::{
+            LinkKind, delete_link, link_details, new_bridge, new_veth_pair, new_vlan, set_master,
+            set_mtu, set_tx_queue_len,
+        },
         socket::netlink::{
-            message::{CMsgSegHdr, CSegmentType, GetRequestFlags, SegHdrCommonFlags},
-            route::message::{LinkAttr, LinkSegment, LinkSegmentBody, RtnlSegment},
+            message::{
+                CMsgSegHdr, CSegmentType, GetRequestFlags, NewRequestFlags, SegHdrCommonFlags,
+            },
+            route::message::{LinkAttr, LinkInfo, LinkSegment, LinkSegmentBody, RtnlSegment},
         },
+        wireguard::{delete_wireguard, is_wireguard, new_wireguard},
     },
     prelude::*,
     util::net::CSocketAddrFamily,
@@ -29,7 +36,7 @@ pub(super) fn do_get_link(request_segment: &LinkSegment) -> Result<Vec<RtnlSegme
             FilterBy::Name(name) => *name == iface.name(),
             FilterBy::Dump => true,
         })
-        .map(|iface| iface_to_new_link(request_segment.header(), iface))
+        .map(|iface| iface_to_new_link(request_segment.header(), &iface))
         .map(RtnlSegment::NewLink)
         .collect();
 
@@ -44,6 +51,202 @@ pub(super) fn do_get_link(request_segment: &LinkSegment) -> Result<Vec<RtnlSegme
     Ok(response_segments)
 }
 
+pub(super) fn do_new_link(request_segment: &LinkSegment) -> Result<Vec<RtnlSegment>> {
+    let flags = NewRequestFlags::from_bits_truncate(request_segment.header().flags);
+
+    // Like Linux, a new link is created if neither the index nor the name is specified.
+    let is_unspecified =
+        request_segment.body().index.is_none() && find_name(request_segment).is_none();
+    let iface = if is_unspecified {
+        None
+    } else {
+        match find_link(request_segment) {
+            Ok(iface) => Some(iface),
+            Err(err) if err.error() == Errno::ENODEV => None,
+            Err(err) => return Err(err),
+        }
+    };
+
+    let Some(iface) = iface else {
+        if !flags.contains(NewRequestFlags::CREATE) {
+            return_errno_with_message!(Errno::ENODEV, "no link found");
+        }
+        create_link(request_segment)?;
+        return Ok(Vec::new());
+    };
+    if flags.contains(NewRequestFlags::EXCL) {
+        return_errno_with_message!(Errno::EEXIST, "the link already exists");
+    }
+
+    change_link(&iface, request_segment)?;
+
+    Ok(Vec::new())
+}
+
+pub(super) fn do_set_link(request_segment: &LinkSegment) -> Result<Vec<RtnlSegment>> {
+    let iface = find_link(request_segment)?;
+
+    change_link(&iface, request_segment)?;
+
+    Ok(Vec::new())
+}
+
+pub(super) fn do_del_link(request_segment: &LinkSegment) -> Result<Vec<RtnlSegment>> {
+    let iface = find_link(request_segment)?;
+
+    // In Linux, only links created via netlink (e.g., virtual links) can be deleted.
+    delete_any_link(iface.index())?;
+
+    Ok(Vec::new())
+}
+
+/// Deletes a link created via netlink.
+fn delete_any_link(index: u32) -> Result<()> {
+    if is_wireguard(index) {
+        delete_wireguard(index)
+    } else {
+        delete_link(index)
+    }
+}
+
+/// Creates a virtual link according to the request.
+fn create_link(request_segment: &LinkSegment) -> Result<()> {
+    let link_info = request_segment.attrs().iter().find_map(|attr| {
+        if let LinkAttr::LinkInfo(link_info) = attr {
+            Some(link_info)
+        } else {
+            None
+        }
+    });
+    let name = find_name(request_segment);
+
+    let index = match link_info.and_then(LinkInfo::kind) {
+        Some("veth") => {
+            let peer_attrs = link_info.unwrap().veth_peer()?.unwrap_or_default();
+            let peer_name = peer_attrs.iter().find_map(|attr| {
+                if let LinkAttr::Name(name) = attr {
+                    Some(name.to_str().unwrap())
+                } else {
+                    None
+                }
+            });
+            new_veth_pair(name, peer_name)?
+        }
+        Some("bridge") => new_bridge(name)?,
+        Some("vlan") => {
+            let Some(parent_index) = request_segment.attrs().iter().find_map(|attr| {
+                if let LinkAttr::Link(parent_index) = attr {
+                    Some(*parent_index)
+                } else {
+                    None
+                }
+            }) else {
+                return_errno_with_message!(Errno::EINVAL, "the parent link is not specified");
+            };
+            let Some(vlan_id) = link_info.unwrap().vlan_id()? else {
+                return_errno_with_message!(Errno::EINVAL, "the VLAN ID is not specified");
+            };
+            new_vlan(name, parent_index, vlan_id)?
+        }
+        Some("wireguard") => new_wireguard(name)?,
+        // TODO: Support other kinds of virtual links (e.g., `macvlan`).
+        _ => return_errno_with_message!(Errno::EOPNOTSUPP, "the link kind is not supported"),
+    };
+
+    let res = request_segment.attrs().iter().try_for_each(|attr| match attr {
+        LinkAttr::Master(master_index) => {
+            set_master(index, NonZero::new(*master_index).map(NonZero::get))
+        }
+        LinkAttr::Mtu(mtu) => set_mtu(index, *mtu as usize),
+        LinkAttr::TxqLen(tx_queue_len) => set_tx_queue_len(index, *tx_queue_len),
+        _ => Ok(()),
+    });
+    if res.is_err() {
+        // The link is newly created, so it can always be deleted.
+        delete_any_link(index).unwrap();
+    }
+
+    res
+}
+
+/// Finds the link specified by the interface index or the interface name in the request.
+fn find_link(request_segment: &LinkSegment) -> Result<Arc<Iface>> {
+    if let Some(required_index) = request_segment.body().index {
+        return find_iface_by_index(required_index.get());
+    }
+
+    let Some(required_name) = find_name(request_segment) else {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "either interface name or index should be specified"
+        );
+    };
+    iter_all_ifaces()
+        .find(|iface| iface.name() == required_name)
+        .ok_or_else(|| Error::with_message(Errno::ENODEV, "no link found"))
+}
+
+/// Changes the link according to the request.
+///
+/// Currently, only the master, the MTU, and the transmit queue length of virtual links can be
+/// changed. Requests to set the flags and the name of the link to their current values still
+/// succeed.
+fn change_link(iface: &Iface, request_segment: &LinkSegment) -> Result<()> {
+    let body = request_segment.body();
+
+    if !body.flags.is_empty() || !body.change.is_empty() {
+        // For backward compatibility, a zero change mask means that all flags should be changed.
+        // This follows `rtnl_dev_combine_flags` in Linux.
+        let new_flags = if body.change.is_empty() {
+            body.flags
+        } else {
+            (body.flags & body.change) | (iface.flags() - body.change)
+        };
+        if new_flags.contains(InterfaceFlags::UP) != iface.flags().contains(InterfaceFlags::UP) {
+            return_errno_with_message!(
+                Errno::EOPNOTSUPP,
+                "bringing the link up or down is not supported"
+            );
+        }
+        // TODO: Support changing other flags (e.g., `PROMISC`).
+    }
+
+    for attr in request_segment.attrs() {
+        match attr {
+            LinkAttr::Mtu(mtu) if *mtu as usize != iface.mtu() => {
+                set_mtu(iface.index(), *mtu as usize)?;
+            }
+            // TODO: Support changing the transmit queue length of other links. For now, the
+            // requests are ignored.
+            LinkAttr::TxqLen(tx_queue_len) if link_details(iface.index()).is_some() => {
+                set_tx_queue_len(iface.index(), *tx_queue_len)?;
+            }
+            LinkAttr::Name(name)
+                if body.index.is_some() && name.as_bytes() != iface.name().as_bytes() =>
+            {
+                return_errno_with_message!(Errno::EOPNOTSUPP, "renaming the link is not supported");
+            }
+            LinkAttr::Master(master_index) => {
+                set_master(iface.index(), NonZero::new(*master_index).map(NonZero::get))?;
+            }
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+/// Finds the interface name in the request.
+fn find_name(request_segment: &LinkSegment) -> Option<&str> {
+    request_segment.attrs().iter().find_map(|attr| {
+        if let LinkAttr::Name(name) = attr {
+            Some(name.to_str().unwrap())
+        } else {
+            None
+        }
+    })
+}
+
 enum FilterBy<'a> {
     Index(u32),
     Name(&'a str),
@@ -69,14 +272,7 @@ impl<'a> FilterBy<'a> {
             return Ok(Self::Index(required_index.get()));
         }
 
-        let required_name = request_segment.attrs().iter().find_map(|attr| {
-            if let LinkAttr::Name(name) = attr {
-                Some(name.to_str().unwrap())
-            } else {
-                None
-            }
-        });
-        if let Some(required_name) = required_name {
+        if let Some(required_name) = find_name(request_segment) {
             return Ok(Self::Name(required_name));
         }
 
@@ -92,8 +288,8 @@ impl<'a> FilterBy<'a> {
 
 fn validate_getlink_request(body: &LinkSegmentBody) -> Result<()> {
     // FIXME: The Linux implementation also checks the `padding` and `change` fields,
-    // but these fields are lost during the conversion of a `CIfInfoMsg` to `LinkSegmentBody`.
-    // We should consider including the `change` field in `LinkSegmentBody`.
+    // but the `padding` field is lost during the conversion of a `CIfInfoMsg` to
+    // `LinkSegmentBody`.
     // Reference: <https://elixir.bootlin.com/linux/v6.13/source/net/core/rtnetlink.c#L4043>.
     if !body.flags.is_empty() || body.type_ != InterfaceType::NETROM {
         return_errno_with_message!(Errno::EINVAL, "the flags or the type is not valid");
@@ -134,12 +330,31 @@ fn iface_to_new_link(request_header: &CMsgSegHdr, iface: &Arc<Iface>) -> LinkSeg
         type_: iface.type_(),
         index: NonZero::new(iface.index()),
         flags: iface.flags(),
+        change: InterfaceFlags::empty(),
     };
 
-    let attrs = vec![
+    let mut attrs = vec![
         LinkAttr::Name(CString::new(iface.name()).unwrap()),
         LinkAttr::Mtu(iface.mtu() as u32),
     ];
 
+    if let Some(details) = link_details(iface.index()) {
+        if let Some(link) = details.link {
+            attrs.push(LinkAttr::Link(link));
+        }
+        if let Some(master) = details.master {
+            attrs.push(LinkAttr::Master(master));
+        }
+        attrs.push(LinkAttr::TxqLen(details.tx_queue_len));
+
+        let link_info = match (details.kind, details.vlan_id) {
+            (LinkKind::Vlan, Some(vlan_id)) => LinkInfo::new_vlan(vlan_id),
+            (kind, _) => LinkInfo::new(kind.name()),
+        };
+        attrs.push(LinkAttr::LinkInfo(link_info));
+    } else if is_wireguard(iface.index()) {
+        attrs.push(LinkAttr::LinkInfo(LinkInfo::new("wireguard")));
+    }
+
     LinkSegment::new(header, link_message, attrs)
 }
@@ -9,14 +9,16 @@ use super::message::{RtnlMessage, RtnlSegment};
 use crate::{
     net::socket::netlink::{
         addr::PortNum,
-        message::{ErrorSegment, ProtocolSegment},
+        message::{ErrorSegment, ProtocolSegment, SegHdrCommonFlags},
         table::{NetlinkRouteProtocol, SupportedNetlinkProtocol},
     },
     prelude::*,
+    process::{credentials::capabilities::CapSet, posix_thread::AsPosixThread},
 };
 
 mod addr;
 mod link;
+mod route;
 mod util;
 
 pub(super) struct NetlinkRouteKernelSocket {
@@ -35,20 +37,27 @@ impl NetlinkRouteKernelSocket {
 
         let request_header = request.header();
 
-        let response_segments = match request {
+        let response_segments = check_permission(request).and_then(|_| match request {
+            RtnlSegment::NewLink(request_segment) => link::do_new_link(request_segment),
+            RtnlSegment::DelLink(request_segment) => link::do_del_link(request_segment),
             RtnlSegment::GetLink(request_segment) => link::do_get_link(request_segment),
+            RtnlSegment::SetLink(request_segment) => link::do_set_link(request_segment),
+            RtnlSegment::NewAddr(request_segment) => addr::do_new_addr(request_segment),
+            RtnlSegment::DelAddr(request_segment) => addr::do_del_addr(request_segment),
             RtnlSegment::GetAddr(request_segment) => addr::do_get_addr(request_segment),
+            RtnlSegment::NewRoute(request_segment) => route::do_new_route(request_segment),
+            RtnlSegment::DelRoute(request_segment) => route::do_del_route(request_segment),
+            RtnlSegment::GetRoute(request_segment) => route::do_get_route(request_segment),
             _ => Err(Error::with_message(
                 Errno::EOPNOTSUPP,
                 "the netlink route request is not supported",
             )),
-        };
+        });
 
-        let response = match response_segments {
-            Ok(segments) => RtnlMessage::new(segments),
+        let mut segments = match response_segments {
+            Ok(segments) => segments,
             Err(error) => {
-                // TODO: Deal with the `NetlinkMessageCommonFlags::ACK` flag.
-                // Should we return `ErrorSegment` if ACK flag does not exist?
+                // Errors are always reported, regardless of the `ACK` flag.
                 // Reference: <https://docs.kernel.org/userspace-api/netlink/intro.html#netlink-message-types>.
                 let err_segment = ErrorSegment::new_from_request(request_header, Some(error));
                 self.report_error(err_segment, dst_port);
@@ -56,6 +65,21 @@ impl NetlinkRouteKernelSocket {
             }
         };
 
+        // Successful requests are acknowledged if the `ACK` flag is set, except for dump requests,
+        // whose responses end with done segments.
+        let flags = SegHdrCommonFlags::from_bits_truncate(request_header.flags);
+        if flags.contains(SegHdrCommonFlags::ACK)
+            && !matches!(segments.last(), Some(RtnlSegment::Done(_)))
+        {
+            let ack_segment = ErrorSegment::new_from_request(request_header, None);
+            segments.push(RtnlSegment::Error(ack_segment));
+        }
+        if segments.is_empty() {
+            return;
+        }
+
+        let response = RtnlMessage::new(segments);
+
         debug!("netlink route response: {:?}", response);
 
         NetlinkRouteProtocol::unicast(dst_port, response).unwrap();
@@ -70,6 +94,28 @@ impl NetlinkRouteKernelSocket {
     }
 }
 
+/// Checks whether the current process is allowed to make the request.
+///
+/// Requests that modify the network configuration require the `CAP_NET_ADMIN` capability.
+fn check_permission(request: &RtnlSegment) -> Result<()> {
+    if matches!(
+        request,
+        RtnlSegment::GetLink(_) | RtnlSegment::GetAddr(_) | RtnlSegment::GetRoute(_)
+    ) {
+        return Ok(());
+    }
+
+    let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+    if !credentials.effective_capset().contains(CapSet::NET_ADMIN) {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "modifying the network configuration requires the CAP_NET_ADMIN capability"
+        );
+    }
+
+    Ok(())
+}
+
 /// FIXME: NETLINK_ROUTE_KERNEL should be a per-network namespace socket
 static NETLINK_ROUTE_KERNEL: NetlinkRouteKernelSocket = NetlinkRouteKernelSocket::new();
 
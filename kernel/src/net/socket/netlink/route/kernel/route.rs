@@ -0,0 +1,345 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Handle route-related requests.
+
+use aster_bigtcp::{
+    iface::InterfaceFlags,
+    wire::{IpAddress, IpCidr, Ipv4Address, Ipv4Cidr, Ipv6Address, Ipv6Cidr},
+};
+
+use super::util::{RtnlGroup, find_iface_by_index, finish_response, iface_addrs, notify};
+use crate::{
+    net::{
+        iface::{Iface, iter_all_ifaces},
+        socket::netlink::{
+            message::{
+                CMsgSegHdr, CSegmentType, GetRequestFlags, NewRequestFlags, SegHdrCommonFlags,
+            },
+            route::message::{
+                RT_TABLE_MAIN, RT_TABLE_UNSPEC, RTPROT_BOOT, RTPROT_KERNEL, RTPROT_RA, RouteAttr,
+                RouteMessageFlags, RouteSegment, RouteSegmentBody, RouteType, RtScope,
+                RtnlSegment,
+            },
+        },
+    },
+    prelude::*,
+    util::net::CSocketAddrFamily,
+};
+
+pub(super) fn do_get_route(request_segment: &RouteSegment) -> Result<Vec<RtnlSegment>> {
+    let dump_all = {
+        let flags = GetRequestFlags::from_bits_truncate(request_segment.header().flags);
+        flags.contains(GetRequestFlags::DUMP)
+    };
+    if !dump_all {
+        return_errno_with_message!(Errno::EOPNOTSUPP, "GETROUTE only supports dump requests");
+    }
+
+    let family = request_segment.body().family;
+    let is_family_matched = |route: &Route| match route.dst {
+        IpCidr::Ipv4(_) => family != CSocketAddrFamily::AF_INET6 as i32,
+        IpCidr::Ipv6(_) => family != CSocketAddrFamily::AF_INET as i32,
+    };
+
+    let mut response_segments: Vec<RtnlSegment> = iter_all_ifaces()
+        .flat_map(|iface| {
+            iface_routes(&iface)
+                .into_iter()
+                .filter(is_family_matched)
+                .map(|route| {
+                    new_route_segment(CSegmentType::NEWROUTE, request_segment.header(), &route)
+                })
+        })
+        .map(RtnlSegment::NewRoute)
+        .collect();
+
+    finish_response(request_segment.header(), dump_all, &mut response_segments);
+
+    Ok(response_segments)
+}
+
+pub(super) fn do_new_route(request_segment: &RouteSegment) -> Result<Vec<RtnlSegment>> {
+    let request = RouteRequest::parse(request_segment)?;
+    let flags = NewRequestFlags::from_bits_truncate(request_segment.header().flags);
+
+    let Some(gateway) = request.gateway else {
+        return_errno_with_message!(
+            Errno::EOPNOTSUPP,
+            "only default routes via gateways are supported"
+        );
+    };
+
+    // The gateway must be reachable on the link, i.e., it must be in the same subnet as one of
+    // the addresses of the iface.
+    let is_on_link = |iface: &Iface| {
+        iface_addrs(iface)
+            .iter()
+            .any(|ip_cidr| ip_cidr.contains_addr(&gateway))
+    };
+    let iface = match request.oif {
+        Some(oif) => find_iface_by_index(oif)?,
+        None => {
+            let Some(iface) = iter_all_ifaces().find(|iface| is_on_link(iface)) else {
+                return_errno_with_message!(Errno::ENETUNREACH, "the gateway is not on link");
+            };
+            iface
+        }
+    };
+    if !is_on_link(&iface) {
+        return_errno_with_message!(Errno::ENETUNREACH, "the gateway is not on link");
+    }
+
+    if default_gateway(&iface, &gateway).is_some() && !flags.contains(NewRequestFlags::REPLACE) {
+        return_errno_with_message!(Errno::EEXIST, "the default route already exists");
+    }
+
+    let group = match gateway {
+        IpAddress::Ipv4(ipv4_gateway) => {
+            iface.set_default_ipv4_route(ipv4_gateway);
+            RtnlGroup::IPV4_ROUTE
+        }
+        IpAddress::Ipv6(ipv6_gateway) => {
+            iface.set_default_ipv6_route(ipv6_gateway);
+            RtnlGroup::IPV6_ROUTE
+        }
+    };
+
+    let route = default_route(&iface, gateway);
+    let segment = new_route_segment(CSegmentType::NEWROUTE, request_segment.header(), &route);
+    notify(group, RtnlSegment::NewRoute(segment));
+
+    Ok(Vec::new())
+}
+
+pub(super) fn do_del_route(request_segment: &RouteSegment) -> Result<Vec<RtnlSegment>> {
+    let request = RouteRequest::parse(request_segment)?;
+
+    // Without the gateway or the output iface, the first default route of the family is deleted.
+    let unspecified_addr = match request.family {
+        CSocketAddrFamily::AF_INET => IpAddress::Ipv4(Ipv4Address::UNSPECIFIED),
+        _ => IpAddress::Ipv6(Ipv6Address::UNSPECIFIED),
+    };
+    let route = iter_all_ifaces()
+        .filter(|iface| request.oif.is_none_or(|oif| iface.index() == oif))
+        .find_map(|iface| {
+            let gateway = default_gateway(&iface, &unspecified_addr)?;
+            if request.gateway.is_some_and(|expected| expected != gateway) {
+                return None;
+            }
+            Some(default_route(&iface, gateway))
+        });
+    let Some(route) = route else {
+        return_errno_with_message!(Errno::ESRCH, "the route does not exist");
+    };
+
+    let iface = find_iface_by_index(route.oif)?;
+    let (is_removed, group) = match route.dst {
+        IpCidr::Ipv4(_) => (iface.remove_default_ipv4_route(), RtnlGroup::IPV4_ROUTE),
+        IpCidr::Ipv6(_) => (iface.remove_default_ipv6_route(), RtnlGroup::IPV6_ROUTE),
+    };
+    if !is_removed {
+        return_errno_with_message!(Errno::ESRCH, "the route does not exist");
+    }
+
+    let segment = new_route_segment(CSegmentType::DELROUTE, request_segment.header(), &route);
+    notify(group, RtnlSegment::DelRoute(segment));
+
+    Ok(Vec::new())
+}
+
+/// A route in the main routing table.
+struct Route {
+    dst: IpCidr,
+    gateway: Option<IpAddress>,
+    pref_src: Option<IpAddress>,
+    priority: Option<u32>,
+    protocol: u8,
+    scope: RtScope,
+    oif: u32,
+}
+
+/// Returns the routes via the iface.
+///
+/// The routes consist of the connected routes, which are implied by the addresses of the iface,
+/// and the default routes.
+fn iface_routes(iface: &Iface) -> Vec<Route> {
+    let mut routes = Vec::new();
+
+    // Linux puts the connected routes of the loopback iface in the local table, which is not
+    // supported, so they are not reported here.
+    if !iface.flags().contains(InterfaceFlags::LOOPBACK)
+        && let Some((ipv4_addr, prefix_len)) = iface.ipv4_addr().zip(iface.prefix_len())
+    {
+        let network = Ipv4Cidr::new(ipv4_addr, prefix_len).network();
+        routes.push(Route {
+            dst: IpCidr::Ipv4(network),
+            gateway: None,
+            pref_src: Some(IpAddress::Ipv4(ipv4_addr)),
+            priority: None,
+            protocol: RTPROT_KERNEL,
+            scope: RtScope::LINK,
+            oif: iface.index(),
+        });
+    }
+
+    for ipv6_cidr in iface.ipv6_addrs() {
+        let prefix_len = ipv6_cidr.prefix_len();
+        let mask = u128::MAX.checked_shl(128 - prefix_len as u32).unwrap_or(0);
+        let network = Ipv6Address::from_bits(ipv6_cidr.address().to_bits() & mask);
+        routes.push(Route {
+            dst: IpCidr::Ipv6(Ipv6Cidr::new(network, prefix_len)),
+            gateway: None,
+            pref_src: None,
+            priority: Some(256),
+            protocol: RTPROT_KERNEL,
+            scope: RtScope::UNIVERSE,
+            oif: iface.index(),
+        });
+    }
+
+    if let Some(ipv4_gateway) = iface.default_ipv4_gateway() {
+        routes.push(default_route(iface, IpAddress::Ipv4(ipv4_gateway)));
+    }
+    if let Some(ipv6_gateway) = iface.default_ipv6_gateway() {
+        routes.push(default_route(iface, IpAddress::Ipv6(ipv6_gateway)));
+    }
+
+    routes
+}
+
+/// Returns the default gateway of the iface whose family is the same as `addr`.
+fn default_gateway(iface: &Iface, addr: &IpAddress) -> Option<IpAddress> {
+    match addr {
+        IpAddress::Ipv4(_) => iface.default_ipv4_gateway().map(IpAddress::Ipv4),
+        IpAddress::Ipv6(_) => iface.default_ipv6_gateway().map(IpAddress::Ipv6),
+    }
+}
+
+fn default_route(iface: &Iface, gateway: IpAddress) -> Route {
+    // The values follow the routes that are added by DHCP clients (for IPv4) and router
+    // advertisements (for IPv6).
+    let (dst, protocol, priority) = match gateway {
+        IpAddress::Ipv4(_) => (
+            IpCidr::Ipv4(Ipv4Cidr::new(Ipv4Address::UNSPECIFIED, 0)),
+            RTPROT_BOOT,
+            None,
+        ),
+        IpAddress::Ipv6(_) => (
+            IpCidr::Ipv6(Ipv6Cidr::new(Ipv6Address::UNSPECIFIED, 0)),
+            RTPROT_RA,
+            Some(1024),
+        ),
+    };
+
+    Route {
+        dst,
+        gateway: Some(gateway),
+        pref_src: None,
+        priority,
+        protocol,
+        scope: RtScope::UNIVERSE,
+        oif: iface.index(),
+    }
+}
+
+fn new_route_segment(
+    type_: CSegmentType,
+    request_header: &CMsgSegHdr,
+    route: &Route,
+) -> RouteSegment {
+    let header = CMsgSegHdr {
+        len: 0,
+        type_: type_ as _,
+        flags: SegHdrCommonFlags::empty().bits(),
+        seq: request_header.seq,
+        pid: request_header.pid,
+    };
+
+    let family = match route.dst {
+        IpCidr::Ipv4(_) => CSocketAddrFamily::AF_INET,
+        IpCidr::Ipv6(_) => CSocketAddrFamily::AF_INET6,
+    };
+    let route_message = RouteSegmentBody {
+        family: family as _,
+        dst_len: route.dst.prefix_len(),
+        src_len: 0,
+        tos: 0,
+        table: RT_TABLE_MAIN,
+        protocol: route.protocol,
+        scope: route.scope,
+        type_: RouteType::UNICAST,
+        flags: RouteMessageFlags::empty(),
+    };
+
+    let mut attrs = vec![RouteAttr::Table(RT_TABLE_MAIN as u32)];
+    if route.dst.prefix_len() != 0 {
+        attrs.push(RouteAttr::Dst(route.dst.address().into()));
+    }
+    if let Some(priority) = route.priority {
+        attrs.push(RouteAttr::Priority(priority));
+    }
+    if let Some(pref_src) = route.pref_src {
+        attrs.push(RouteAttr::PrefSrc(pref_src.into()));
+    }
+    if let Some(gateway) = route.gateway {
+        attrs.push(RouteAttr::Gateway(gateway.into()));
+    }
+    attrs.push(RouteAttr::Oif(route.oif));
+
+    RouteSegment::new(header, route_message, attrs)
+}
+
+/// A parsed request to add or delete a route.
+struct RouteRequest {
+    family: CSocketAddrFamily,
+    gateway: Option<IpAddress>,
+    oif: Option<u32>,
+}
+
+impl RouteRequest {
+    fn parse(request_segment: &RouteSegment) -> Result<Self> {
+        let body = request_segment.body();
+
+        let family = match CSocketAddrFamily::try_from(body.family) {
+            Ok(family @ (CSocketAddrFamily::AF_INET | CSocketAddrFamily::AF_INET6)) => family,
+            _ => return_errno_with_message!(Errno::EAFNOSUPPORT, "the family is not supported"),
+        };
+
+        let mut table = body.table as u32;
+        let mut gateway = None;
+        let mut oif = None;
+        for attr in request_segment.attrs() {
+            match attr {
+                RouteAttr::Table(attr_table) => table = *attr_table,
+                RouteAttr::Gateway(attr_gateway) => gateway = Some(IpAddress::from(*attr_gateway)),
+                RouteAttr::Oif(attr_oif) => oif = Some(*attr_oif),
+                // TODO: Support other attributes.
+                _ => (),
+            }
+        }
+
+        if table != RT_TABLE_MAIN as u32 && table != RT_TABLE_UNSPEC as u32 {
+            return_errno_with_message!(Errno::EOPNOTSUPP, "only the main table is supported");
+        }
+        if body.dst_len != 0 {
+            return_errno_with_message!(Errno::EOPNOTSUPP, "only default routes are supported");
+        }
+        if body.type_ != RouteType::UNSPEC && body.type_ != RouteType::UNICAST {
+            return_errno_with_message!(Errno::EOPNOTSUPP, "only unicast routes are supported");
+        }
+
+        let is_gateway_matched = |gateway: &IpAddress| match gateway {
+            IpAddress::Ipv4(_) => family == CSocketAddrFamily::AF_INET,
+            IpAddress::Ipv6(_) => family == CSocketAddrFamily::AF_INET6,
+        };
+        if gateway.is_some_and(|gateway| !is_gateway_matched(&gateway)) {
+            return_errno_with_message!(Errno::EINVAL, "the gateway does not match the family");
+        }
+
+        Ok(Self {
+            family,
+            gateway,
+            oif,
+        })
+    }
+}
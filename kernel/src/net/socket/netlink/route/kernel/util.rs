@@ -1,9 +1,16 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use aster_bigtcp::wire::IpCidr;
+
 use crate::{
-    net::socket::netlink::{
-        message::{CMsgSegHdr, DoneSegment, ProtocolSegment, SegHdrCommonFlags},
-        route::message::RtnlSegment,
+    net::{
+        iface::{Iface, iter_all_ifaces},
+        socket::netlink::{
+            GroupIdSet,
+            message::{CMsgSegHdr, DoneSegment, ProtocolSegment, SegHdrCommonFlags},
+            route::message::{RtnlMessage, RtnlSegment},
+            table::{NetlinkRouteProtocol, SupportedNetlinkProtocol},
+        },
     },
     prelude::*,
 };
@@ -37,3 +44,42 @@ fn add_multi_flag(response_segments: &mut [RtnlSegment]) {
         header.flags = flags.bits();
     }
 }
+
+/// Finds the iface with the index.
+pub fn find_iface_by_index(index: u32) -> Result<Arc<Iface>> {
+    iter_all_ifaces()
+        .find(|iface| iface.index() == index)
+        .ok_or_else(|| Error::with_message(Errno::ENODEV, "the interface does not exist"))
+}
+
+/// Returns all the IP addresses of the iface.
+pub fn iface_addrs(iface: &Iface) -> Vec<IpCidr> {
+    let ipv4_cidr = iface
+        .ipv4_addr()
+        .zip(iface.prefix_len())
+        .map(|(ipv4_addr, prefix_len)| IpCidr::new(ipv4_addr.into(), prefix_len));
+    let ipv6_cidrs = iface.ipv6_addrs().into_iter().map(IpCidr::Ipv6);
+
+    ipv4_cidr.into_iter().chain(ipv6_cidrs).collect()
+}
+
+/// Multicast groups of the netlink route protocol.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/rtnetlink.h#L729>.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy)]
+#[expect(non_camel_case_types)]
+#[expect(clippy::upper_case_acronyms)]
+pub enum RtnlGroup {
+    IPV4_IFADDR = 5,
+    IPV4_ROUTE = 7,
+    IPV6_IFADDR = 9,
+    IPV6_ROUTE = 11,
+}
+
+/// Notifies the sockets in the multicast group of a change in the network configuration.
+pub fn notify(group: RtnlGroup, segment: RtnlSegment) {
+    let groups = GroupIdSet::new(1 << (group as u32 - 1));
+    let message = RtnlMessage::new(vec![segment]);
+    NetlinkRouteProtocol::multicast(groups, message).unwrap();
+}
@@ -1,5 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use super::{IFNAME_SIZE, IpAddrPayload};
 use crate::{
     net::socket::netlink::message::{Attribute, CAttrHeader, ContinueRead},
     prelude::*,
@@ -27,10 +28,10 @@ enum AddrAttrClass {
     TARGET_NETNSID = 10,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum AddrAttr {
-    Address([u8; 4]),
-    Local([u8; 4]),
+    Address(IpAddrPayload),
+    Local(IpAddrPayload),
     Label(CString),
 }
 
@@ -51,8 +52,8 @@ impl Attribute for AddrAttr {
 
     fn payload_as_bytes(&self) -> &[u8] {
         match self {
-            AddrAttr::Address(address) => address,
-            AddrAttr::Local(local) => local,
+            AddrAttr::Address(address) => address.as_bytes(),
+            AddrAttr::Local(local) => local.as_bytes(),
             AddrAttr::Label(label) => label.as_bytes_with_nul(),
         }
     }
@@ -62,26 +63,58 @@ impl Attribute for AddrAttr {
         Self: Sized,
     {
         let payload_len = header.payload_len();
-        reader.skip_some(payload_len);
 
-        // GETADDR only supports dump requests. These requests do not have any attributes.
-        // According to the Linux behavior, we should just ignore all the attributes.
+        let Ok(class) = AddrAttrClass::try_from(header.type_()) else {
+            // Unknown attributes should be ignored.
+            // Reference: <https://docs.kernel.org/userspace-api/netlink/intro.html#unknown-attributes>.
+            reader.skip_some(payload_len);
+            return Ok(ContinueRead::Skipped);
+        };
 
-        Ok(ContinueRead::Skipped)
-    }
-
-    fn read_all_from(
-        reader: &mut dyn MultiRead,
-        total_len: usize,
-    ) -> Result<ContinueRead<Vec<Self>>>
-    where
-        Self: Sized,
-    {
-        reader.skip_some(total_len);
-
-        // GETADDR only supports dump requests. These requests do not have any attributes.
-        // According to the Linux behavior, we should just ignore all the attributes.
+        let res = match class {
+            AddrAttrClass::ADDRESS | AddrAttrClass::LOCAL => {
+                let Some(addr) = IpAddrPayload::read_from(reader, payload_len)? else {
+                    warn!("address attribute `{:?}` contains invalid payload", class);
+                    reader.skip_some(payload_len);
+                    return Ok(ContinueRead::skipped_with_error(
+                        Errno::EINVAL,
+                        "the address attribute is invalid",
+                    ));
+                };
+                if class == AddrAttrClass::ADDRESS {
+                    Self::Address(addr)
+                } else {
+                    Self::Local(addr)
+                }
+            }
+            AddrAttrClass::LABEL if (1..=IFNAME_SIZE).contains(&payload_len) => {
+                let (label, label_len) = reader.read_cstring_until_end(payload_len)?;
+                if label_len != payload_len {
+                    reader.skip_some(payload_len - label_len);
+                }
+                if label.as_bytes().len() == IFNAME_SIZE {
+                    return Ok(ContinueRead::skipped_with_error(
+                        Errno::ERANGE,
+                        "the address attribute is invalid",
+                    ));
+                }
+                Self::Label(label)
+            }
+            AddrAttrClass::LABEL => {
+                warn!("address attribute `{:?}` contains invalid payload", class);
+                reader.skip_some(payload_len);
+                return Ok(ContinueRead::skipped_with_error(
+                    Errno::ERANGE,
+                    "the address attribute is invalid",
+                ));
+            }
+            _ => {
+                warn!("address attribute `{:?}` is not supported", class);
+                reader.skip_some(payload_len);
+                return Ok(ContinueRead::Skipped);
+            }
+        };
 
-        Ok(ContinueRead::Skipped)
+        Ok(ContinueRead::Parsed(res))
     }
 }
@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use super::IFNAME_SIZE;
+use super::{IFNAME_SIZE, link_info::LinkInfo};
 use crate::{
     net::socket::netlink::message::{Attribute, CAttrHeader, ContinueRead},
     prelude::*,
@@ -82,12 +82,19 @@ enum LinkAttrClass {
     PARENT_DEV_BUS_NAME = 57,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum LinkAttr {
     Name(CString),
     Mtu(u32),
+    /// The index of the peer link (e.g., the other end of a veth pair).
+    Link(u32),
+    /// The index of the master link (e.g., the bridge that the link is attached to).
+    ///
+    /// When changing a link, an index of zero detaches the link from its master.
+    Master(u32),
     TxqLen(u32),
     LinkMode(u8),
+    LinkInfo(LinkInfo),
     ExtMask(RtExtFilter),
 }
 
@@ -96,8 +103,11 @@ impl LinkAttr {
         match self {
             LinkAttr::Name(_) => LinkAttrClass::IFNAME,
             LinkAttr::Mtu(_) => LinkAttrClass::MTU,
+            LinkAttr::Link(_) => LinkAttrClass::LINK,
+            LinkAttr::Master(_) => LinkAttrClass::MASTER,
             LinkAttr::TxqLen(_) => LinkAttrClass::TXQLEN,
             LinkAttr::LinkMode(_) => LinkAttrClass::LINKMODE,
+            LinkAttr::LinkInfo(_) => LinkAttrClass::LINKINFO,
             LinkAttr::ExtMask(_) => LinkAttrClass::EXT_MASK,
         }
     }
@@ -112,8 +122,11 @@ impl Attribute for LinkAttr {
         match self {
             LinkAttr::Name(name) => name.as_bytes_with_nul(),
             LinkAttr::Mtu(mtu) => mtu.as_bytes(),
+            LinkAttr::Link(index) => index.as_bytes(),
+            LinkAttr::Master(index) => index.as_bytes(),
             LinkAttr::TxqLen(txq_len) => txq_len.as_bytes(),
             LinkAttr::LinkMode(link_mode) => link_mode.as_bytes(),
+            LinkAttr::LinkInfo(link_info) => link_info.as_bytes(),
             LinkAttr::ExtMask(ext_filter) => ext_filter.as_bytes(),
         }
     }
@@ -148,16 +161,25 @@ impl Attribute for LinkAttr {
                 Self::Name(name)
             }
             (LinkAttrClass::MTU, 4) => Self::Mtu(reader.read_val_opt::<u32>()?.unwrap()),
+            (LinkAttrClass::LINK, 4) => Self::Link(reader.read_val_opt::<u32>()?.unwrap()),
+            (LinkAttrClass::MASTER, 4) => Self::Master(reader.read_val_opt::<u32>()?.unwrap()),
             (LinkAttrClass::TXQLEN, 4) => Self::TxqLen(reader.read_val_opt::<u32>()?.unwrap()),
             (LinkAttrClass::LINKMODE, 1) => Self::LinkMode(reader.read_val_opt::<u8>()?.unwrap()),
             (LinkAttrClass::EXT_MASK, 4) => {
                 const { assert!(size_of::<RtExtFilter>() == 4) };
                 Self::ExtMask(reader.read_val_opt::<RtExtFilter>()?.unwrap())
             }
+            (LinkAttrClass::LINKINFO, _) => match LinkInfo::read_from(reader, payload_len)? {
+                ContinueRead::Parsed(link_info) => Self::LinkInfo(link_info),
+                ContinueRead::Skipped => return Ok(ContinueRead::Skipped),
+                ContinueRead::SkippedErr(err) => return Ok(ContinueRead::SkippedErr(err)),
+            },
 
             (
                 LinkAttrClass::IFNAME
                 | LinkAttrClass::MTU
+                | LinkAttrClass::LINK
+                | LinkAttrClass::MASTER
                 | LinkAttrClass::TXQLEN
                 | LinkAttrClass::LINKMODE
                 | LinkAttrClass::EXT_MASK,
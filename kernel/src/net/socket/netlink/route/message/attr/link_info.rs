@@ -0,0 +1,365 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The attributes nested in [`LinkAttr::LinkInfo`], which describe the kinds of virtual links.
+//!
+//! [`LinkAttr::LinkInfo`]: super::link::LinkAttr::LinkInfo
+
+use super::link::LinkAttr;
+use crate::{
+    net::socket::netlink::{
+        message::{Attribute, CAttrHeader, ContinueRead},
+        route::message::segment::link::CIfinfoMsg,
+    },
+    prelude::*,
+    util::MultiRead,
+};
+
+/// The link information (`IFLA_LINKINFO`).
+#[derive(Debug, Clone)]
+pub struct LinkInfo {
+    kind: Option<CString>,
+    /// The kind-specific data, which contains attributes nested in `IFLA_INFO_DATA`.
+    data: Option<Vec<u8>>,
+    /// The raw payload of the attribute.
+    payload: Vec<u8>,
+}
+
+impl LinkInfo {
+    /// Creates the link information with only the kind.
+    pub fn new(kind: &str) -> Self {
+        Self::new_with_data(kind, None)
+    }
+
+    /// Creates the link information of a VLAN link with the VLAN ID.
+    pub fn new_vlan(vlan_id: u16) -> Self {
+        let data = encode_attrs(&[
+            VlanInfoAttr::Id(vlan_id),
+            VlanInfoAttr::Protocol(ETH_P_8021Q.to_be()),
+        ]);
+        Self::new_with_data("vlan", Some(data))
+    }
+
+    fn new_with_data(kind: &str, data: Option<Vec<u8>>) -> Self {
+        let kind = CString::new(kind).unwrap();
+
+        let mut attrs = vec![LinkInfoAttr::Kind(kind.clone())];
+        if let Some(data) = data.as_ref() {
+            attrs.push(LinkInfoAttr::Data(data.clone()));
+        }
+        let payload = encode_attrs(&attrs);
+
+        Self {
+            kind: Some(kind),
+            data,
+            payload,
+        }
+    }
+
+    /// Returns the kind of the link (e.g., `veth` and `bridge`).
+    pub fn kind(&self) -> Option<&str> {
+        self.kind.as_ref().and_then(|kind| kind.to_str().ok())
+    }
+
+    /// Returns the attributes of the peer link, if the link is a veth link and the peer is
+    /// specified.
+    pub fn veth_peer(&self) -> Result<Option<Vec<LinkAttr>>> {
+        let Some(data) = self.data.as_ref() else {
+            return Ok(None);
+        };
+
+        let mut reader = VmReader::from(data.as_slice()).to_fallible();
+        let attrs = match VethInfoAttr::read_all_from(&mut reader, data.len())? {
+            ContinueRead::Parsed(attrs) => attrs,
+            ContinueRead::Skipped => Vec::new(),
+            ContinueRead::SkippedErr(err) => return Err(err),
+        };
+
+        let Some(VethInfoAttr::Peer(peer)) = attrs.first() else {
+            return Ok(None);
+        };
+
+        // TODO: Support specifying the flags of the peer link in `struct ifinfomsg`.
+        let mut reader = VmReader::from(&peer[size_of::<CIfinfoMsg>()..]).to_fallible();
+        let attrs_len = peer.len() - size_of::<CIfinfoMsg>();
+        match LinkAttr::read_all_from(&mut reader, attrs_len)? {
+            ContinueRead::Parsed(peer_attrs) => Ok(Some(peer_attrs)),
+            ContinueRead::Skipped => Ok(Some(Vec::new())),
+            ContinueRead::SkippedErr(err) => Err(err),
+        }
+    }
+
+    /// Returns the VLAN ID, if the link is a VLAN link and the VLAN ID is specified.
+    pub fn vlan_id(&self) -> Result<Option<u16>> {
+        let Some(data) = self.data.as_ref() else {
+            return Ok(None);
+        };
+
+        let mut reader = VmReader::from(data.as_slice()).to_fallible();
+        let attrs = match VlanInfoAttr::read_all_from(&mut reader, data.len())? {
+            ContinueRead::Parsed(attrs) => attrs,
+            ContinueRead::Skipped => Vec::new(),
+            ContinueRead::SkippedErr(err) => return Err(err),
+        };
+
+        let mut vlan_id = None;
+        for attr in attrs {
+            match attr {
+                VlanInfoAttr::Id(id) => vlan_id = Some(id),
+                VlanInfoAttr::Protocol(protocol) if protocol == ETH_P_8021Q.to_be() => (),
+                // TODO: Support 802.1ad (QinQ) VLAN links.
+                VlanInfoAttr::Protocol(_) => {
+                    return_errno_with_message!(
+                        Errno::EPROTONOSUPPORT,
+                        "the VLAN protocol is not supported"
+                    );
+                }
+            }
+        }
+
+        Ok(vlan_id)
+    }
+
+    pub(super) fn as_bytes(&self) -> &[u8] {
+        &self.payload
+    }
+
+    pub(super) fn read_from(
+        reader: &mut dyn MultiRead,
+        payload_len: usize,
+    ) -> Result<ContinueRead<Self>> {
+        let mut payload = vec![0u8; payload_len];
+        reader.read(&mut VmWriter::from(payload.as_mut_slice()))?;
+
+        let mut payload_reader = VmReader::from(payload.as_slice()).to_fallible();
+        let attrs = match LinkInfoAttr::read_all_from(&mut payload_reader, payload_len)? {
+            ContinueRead::Parsed(attrs) => attrs,
+            ContinueRead::Skipped => Vec::new(),
+            ContinueRead::SkippedErr(err) => return Ok(ContinueRead::SkippedErr(err)),
+        };
+
+        let mut kind = None;
+        let mut data = None;
+        for attr in attrs {
+            match attr {
+                LinkInfoAttr::Kind(attr_kind) => kind = Some(attr_kind),
+                LinkInfoAttr::Data(attr_data) => data = Some(attr_data),
+            }
+        }
+
+        Ok(ContinueRead::Parsed(Self {
+            kind,
+            data,
+            payload,
+        }))
+    }
+}
+
+/// Link information attributes.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/if_link.h#L1148>.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+#[repr(u16)]
+#[expect(non_camel_case_types)]
+#[expect(clippy::upper_case_acronyms)]
+enum LinkInfoAttrClass {
+    UNSPEC = 0,
+    KIND = 1,
+    DATA = 2,
+    XSTATS = 3,
+    SLAVE_KIND = 4,
+    SLAVE_DATA = 5,
+}
+
+#[derive(Debug, Clone)]
+enum LinkInfoAttr {
+    Kind(CString),
+    /// The raw payload of the kind-specific data.
+    Data(Vec<u8>),
+}
+
+impl Attribute for LinkInfoAttr {
+    fn type_(&self) -> u16 {
+        match self {
+            LinkInfoAttr::Kind(_) => LinkInfoAttrClass::KIND as u16,
+            LinkInfoAttr::Data(_) => LinkInfoAttrClass::DATA as u16,
+        }
+    }
+
+    fn payload_as_bytes(&self) -> &[u8] {
+        match self {
+            LinkInfoAttr::Kind(kind) => kind.as_bytes_with_nul(),
+            LinkInfoAttr::Data(data) => data,
+        }
+    }
+
+    fn read_from(header: &CAttrHeader, reader: &mut dyn MultiRead) -> Result<ContinueRead<Self>>
+    where
+        Self: Sized,
+    {
+        let payload_len = header.payload_len();
+
+        let res = match LinkInfoAttrClass::try_from(header.type_()) {
+            Ok(LinkInfoAttrClass::KIND) => {
+                let (kind, kind_len) = reader.read_cstring_until_end(payload_len)?;
+                reader.skip_some(payload_len - kind_len);
+                Self::Kind(kind)
+            }
+            Ok(LinkInfoAttrClass::DATA) => {
+                let mut data = vec![0u8; payload_len];
+                reader.read(&mut VmWriter::from(data.as_mut_slice()))?;
+                Self::Data(data)
+            }
+            // TODO: Support the attributes for bridge ports (e.g., `IFLA_INFO_SLAVE_KIND`).
+            _ => {
+                reader.skip_some(payload_len);
+                return Ok(ContinueRead::Skipped);
+            }
+        };
+
+        Ok(ContinueRead::Parsed(res))
+    }
+}
+
+/// Veth attributes, which are nested in `IFLA_INFO_DATA` of veth links.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/veth.h#L5>.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+#[repr(u16)]
+#[expect(non_camel_case_types)]
+#[expect(clippy::upper_case_acronyms)]
+enum VethInfoAttrClass {
+    UNSPEC = 0,
+    PEER = 1,
+}
+
+#[derive(Debug, Clone)]
+enum VethInfoAttr {
+    /// The raw payload of the peer link.
+    ///
+    /// The payload starts with a `struct ifinfomsg`, which is followed by the link attributes.
+    Peer(Vec<u8>),
+}
+
+impl Attribute for VethInfoAttr {
+    fn type_(&self) -> u16 {
+        match self {
+            VethInfoAttr::Peer(_) => VethInfoAttrClass::PEER as u16,
+        }
+    }
+
+    fn payload_as_bytes(&self) -> &[u8] {
+        match self {
+            VethInfoAttr::Peer(peer) => peer,
+        }
+    }
+
+    fn read_from(header: &CAttrHeader, reader: &mut dyn MultiRead) -> Result<ContinueRead<Self>>
+    where
+        Self: Sized,
+    {
+        let payload_len = header.payload_len();
+
+        match VethInfoAttrClass::try_from(header.type_()) {
+            Ok(VethInfoAttrClass::PEER) if payload_len >= size_of::<CIfinfoMsg>() => {
+                let mut peer = vec![0u8; payload_len];
+                reader.read(&mut VmWriter::from(peer.as_mut_slice()))?;
+                Ok(ContinueRead::Parsed(Self::Peer(peer)))
+            }
+            Ok(VethInfoAttrClass::PEER) => {
+                reader.skip_some(payload_len);
+                Ok(ContinueRead::skipped_with_error(
+                    Errno::EINVAL,
+                    "the veth peer attribute is too short",
+                ))
+            }
+            _ => {
+                reader.skip_some(payload_len);
+                Ok(ContinueRead::Skipped)
+            }
+        }
+    }
+}
+
+/// VLAN attributes, which are nested in `IFLA_INFO_DATA` of VLAN links.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/if_link.h#L1184>.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+#[repr(u16)]
+#[expect(non_camel_case_types)]
+#[expect(clippy::upper_case_acronyms)]
+enum VlanInfoAttrClass {
+    UNSPEC = 0,
+    ID = 1,
+    FLAGS = 2,
+    EGRESS_QOS = 3,
+    INGRESS_QOS = 4,
+    PROTOCOL = 5,
+}
+
+#[derive(Debug, Clone)]
+enum VlanInfoAttr {
+    Id(u16),
+    /// The protocol of the VLAN tags (in network byte order).
+    Protocol(u16),
+}
+
+impl Attribute for VlanInfoAttr {
+    fn type_(&self) -> u16 {
+        match self {
+            VlanInfoAttr::Id(_) => VlanInfoAttrClass::ID as u16,
+            VlanInfoAttr::Protocol(_) => VlanInfoAttrClass::PROTOCOL as u16,
+        }
+    }
+
+    fn payload_as_bytes(&self) -> &[u8] {
+        match self {
+            VlanInfoAttr::Id(id) => id.as_bytes(),
+            VlanInfoAttr::Protocol(protocol) => protocol.as_bytes(),
+        }
+    }
+
+    fn read_from(header: &CAttrHeader, reader: &mut dyn MultiRead) -> Result<ContinueRead<Self>>
+    where
+        Self: Sized,
+    {
+        let payload_len = header.payload_len();
+
+        let res = match (VlanInfoAttrClass::try_from(header.type_()), payload_len) {
+            (Ok(VlanInfoAttrClass::ID), 2) => Self::Id(reader.read_val_opt::<u16>()?.unwrap()),
+            (Ok(VlanInfoAttrClass::PROTOCOL), 2) => {
+                Self::Protocol(reader.read_val_opt::<u16>()?.unwrap())
+            }
+            (Ok(VlanInfoAttrClass::ID | VlanInfoAttrClass::PROTOCOL), _) => {
+                reader.skip_some(payload_len);
+                return Ok(ContinueRead::skipped_with_error(
+                    Errno::EINVAL,
+                    "the VLAN attribute is invalid",
+                ));
+            }
+            // TODO: Support the VLAN flags and the QoS mappings.
+            _ => {
+                reader.skip_some(payload_len);
+                return Ok(ContinueRead::Skipped);
+            }
+        };
+
+        Ok(ContinueRead::Parsed(res))
+    }
+}
+
+/// The protocol of 802.1Q VLAN tags.
+const ETH_P_8021Q: u16 = 0x8100;
+
+/// Encodes the attributes as the payload of a nested attribute.
+fn encode_attrs<A: Attribute>(attrs: &[A]) -> Vec<u8> {
+    let len = attrs.iter().map(A::total_len_with_padding).sum();
+    let mut payload = vec![0u8; len];
+
+    let mut writer = VmWriter::from(payload.as_mut_slice()).to_fallible();
+    for attr in attrs {
+        // Writing to the kernel memory never fails.
+        attr.write_to(&mut writer).unwrap();
+    }
+
+    payload
+}
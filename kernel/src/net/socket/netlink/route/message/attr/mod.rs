@@ -1,7 +1,63 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use aster_bigtcp::wire::{IpAddress, Ipv4Address, Ipv6Address};
+
+use crate::{prelude::*, util::MultiRead};
+
 pub mod addr;
 pub mod link;
+pub mod link_info;
+pub mod route;
 
 /// The size limit for interface names.
 const IFNAME_SIZE: usize = 16;
+
+/// An IP address in the payload of an attribute.
+///
+/// The payload length depends on the address family.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpAddrPayload {
+    Ipv4([u8; 4]),
+    Ipv6([u8; 16]),
+}
+
+impl IpAddrPayload {
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            IpAddrPayload::Ipv4(addr) => addr,
+            IpAddrPayload::Ipv6(addr) => addr,
+        }
+    }
+
+    /// Reads the IP address from the `reader`.
+    ///
+    /// This method returns `None` without reading anything if `payload_len` is not the length of
+    /// an IPv4 address or an IPv6 address.
+    fn read_from(reader: &mut dyn MultiRead, payload_len: usize) -> Result<Option<Self>> {
+        let addr = match payload_len {
+            4 => Self::Ipv4(reader.read_val_opt::<[u8; 4]>()?.unwrap()),
+            16 => Self::Ipv6(reader.read_val_opt::<[u8; 16]>()?.unwrap()),
+            _ => return Ok(None),
+        };
+
+        Ok(Some(addr))
+    }
+}
+
+impl From<IpAddress> for IpAddrPayload {
+    fn from(value: IpAddress) -> Self {
+        match value {
+            IpAddress::Ipv4(ipv4_addr) => Self::Ipv4(ipv4_addr.octets()),
+            IpAddress::Ipv6(ipv6_addr) => Self::Ipv6(ipv6_addr.octets()),
+        }
+    }
+}
+
+impl From<IpAddrPayload> for IpAddress {
+    fn from(value: IpAddrPayload) -> Self {
+        match value {
+            IpAddrPayload::Ipv4(octets) => IpAddress::Ipv4(Ipv4Address::from(octets)),
+            IpAddrPayload::Ipv6(octets) => IpAddress::Ipv6(Ipv6Address::from(octets)),
+        }
+    }
+}
@@ -0,0 +1,144 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::IpAddrPayload;
+use crate::{
+    net::socket::netlink::message::{Attribute, CAttrHeader, ContinueRead},
+    prelude::*,
+    util::MultiRead,
+};
+
+/// Route-related attributes.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/rtnetlink.h#L358>.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+#[repr(u16)]
+#[expect(non_camel_case_types)]
+#[expect(clippy::upper_case_acronyms)]
+enum RouteAttrClass {
+    UNSPEC = 0,
+    DST = 1,
+    SRC = 2,
+    IIF = 3,
+    OIF = 4,
+    GATEWAY = 5,
+    PRIORITY = 6,
+    PREFSRC = 7,
+    METRICS = 8,
+    MULTIPATH = 9,
+    /// No longer used
+    PROTOINFO = 10,
+    FLOW = 11,
+    CACHEINFO = 12,
+    /// No longer used
+    SESSION = 13,
+    /// No longer used
+    MP_ALGO = 14,
+    TABLE = 15,
+    MARK = 16,
+    MFC_STATS = 17,
+    VIA = 18,
+    NEWDST = 19,
+    PREF = 20,
+    ENCAP_TYPE = 21,
+    ENCAP = 22,
+    EXPIRES = 23,
+    PAD = 24,
+    UID = 25,
+    TTL_PROPAGATE = 26,
+    IP_PROTO = 27,
+    SPORT = 28,
+    DPORT = 29,
+    NH_ID = 30,
+}
+
+#[derive(Debug, Clone)]
+pub enum RouteAttr {
+    Dst(IpAddrPayload),
+    Oif(u32),
+    Gateway(IpAddrPayload),
+    Priority(u32),
+    PrefSrc(IpAddrPayload),
+    Table(u32),
+}
+
+impl RouteAttr {
+    fn class(&self) -> RouteAttrClass {
+        match self {
+            RouteAttr::Dst(_) => RouteAttrClass::DST,
+            RouteAttr::Oif(_) => RouteAttrClass::OIF,
+            RouteAttr::Gateway(_) => RouteAttrClass::GATEWAY,
+            RouteAttr::Priority(_) => RouteAttrClass::PRIORITY,
+            RouteAttr::PrefSrc(_) => RouteAttrClass::PREFSRC,
+            RouteAttr::Table(_) => RouteAttrClass::TABLE,
+        }
+    }
+}
+
+impl Attribute for RouteAttr {
+    fn type_(&self) -> u16 {
+        self.class() as u16
+    }
+
+    fn payload_as_bytes(&self) -> &[u8] {
+        match self {
+            RouteAttr::Dst(dst) => dst.as_bytes(),
+            RouteAttr::Oif(oif) => oif.as_bytes(),
+            RouteAttr::Gateway(gateway) => gateway.as_bytes(),
+            RouteAttr::Priority(priority) => priority.as_bytes(),
+            RouteAttr::PrefSrc(pref_src) => pref_src.as_bytes(),
+            RouteAttr::Table(table) => table.as_bytes(),
+        }
+    }
+
+    fn read_from(header: &CAttrHeader, reader: &mut dyn MultiRead) -> Result<ContinueRead<Self>>
+    where
+        Self: Sized,
+    {
+        let payload_len = header.payload_len();
+
+        let Ok(class) = RouteAttrClass::try_from(header.type_()) else {
+            // Unknown attributes should be ignored.
+            // Reference: <https://docs.kernel.org/userspace-api/netlink/intro.html#unknown-attributes>.
+            reader.skip_some(payload_len);
+            return Ok(ContinueRead::Skipped);
+        };
+
+        let res = match (class, payload_len) {
+            (RouteAttrClass::DST | RouteAttrClass::GATEWAY | RouteAttrClass::PREFSRC, _) => {
+                let Some(addr) = IpAddrPayload::read_from(reader, payload_len)? else {
+                    warn!("route attribute `{:?}` contains invalid payload", class);
+                    reader.skip_some(payload_len);
+                    return Ok(ContinueRead::skipped_with_error(
+                        Errno::EINVAL,
+                        "the route attribute is invalid",
+                    ));
+                };
+                match class {
+                    RouteAttrClass::DST => Self::Dst(addr),
+                    RouteAttrClass::GATEWAY => Self::Gateway(addr),
+                    _ => Self::PrefSrc(addr),
+                }
+            }
+            (RouteAttrClass::OIF, 4) => Self::Oif(reader.read_val_opt::<u32>()?.unwrap()),
+            (RouteAttrClass::PRIORITY, 4) => Self::Priority(reader.read_val_opt::<u32>()?.unwrap()),
+            (RouteAttrClass::TABLE, 4) => Self::Table(reader.read_val_opt::<u32>()?.unwrap()),
+
+            (RouteAttrClass::OIF | RouteAttrClass::PRIORITY | RouteAttrClass::TABLE, _) => {
+                warn!("route attribute `{:?}` contains invalid payload", class);
+                reader.skip_some(payload_len);
+                return Ok(ContinueRead::skipped_with_error(
+                    Errno::EINVAL,
+                    "the route attribute is invalid",
+                ));
+            }
+
+            (_, _) => {
+                warn!("route attribute `{:?}` is not supported", class);
+                reader.skip_some(payload_len);
+                return Ok(ContinueRead::Skipped);
+            }
+        };
+
+        Ok(ContinueRead::Parsed(res))
+    }
+}
@@ -8,14 +8,22 @@
 mod attr;
 mod segment;
 
-pub(super) use attr::{addr::AddrAttr, link::LinkAttr};
+pub(super) use attr::{
+    IpAddrPayload, addr::AddrAttr, link::LinkAttr, link_info::LinkInfo, route::RouteAttr,
+};
 pub(super) use segment::{
     RtnlSegment,
     addr::{AddrMessageFlags, AddrSegment, AddrSegmentBody, RtScope},
     link::{LinkSegment, LinkSegmentBody},
+    route::{
+        RT_TABLE_MAIN, RT_TABLE_UNSPEC, RTPROT_BOOT, RTPROT_KERNEL, RTPROT_RA, RouteMessageFlags,
+        RouteSegment, RouteSegmentBody, RouteType,
+    },
 };
 
-use crate::net::socket::netlink::message::Message;
+use crate::net::socket::netlink::{message::Message, table::MulticastMessage};
 
 /// A netlink route message.
 pub(in crate::net::socket::netlink) type RtnlMessage = Message<RtnlSegment>;
+
+impl MulticastMessage for RtnlMessage {}
@@ -10,10 +10,11 @@ use super::{
 };
 use crate::{
     net::socket::netlink::{
-        addr::UNSPECIFIED_PORT, kobject_uevent::UeventMessage, receiver::MessageReceiver,
-        route::RtnlMessage,
+        addr::UNSPECIFIED_PORT, audit::AuditMessage, generic::GenlMessage,
+        kobject_uevent::UeventMessage, receiver::MessageReceiver, route::RtnlMessage,
     },
     prelude::*,
+    process::{credentials::capabilities::CapSet, posix_thread::AsPosixThread},
     util::random::getrandom,
 };
 
@@ -25,6 +26,8 @@ static NETLINK_SOCKET_TABLE: Once<NetlinkSocketTable> = Once::new();
 struct NetlinkSocketTable {
     route: RwMutex<ProtocolSocketTable<RtnlMessage>>,
     uevent: RwMutex<ProtocolSocketTable<UeventMessage>>,
+    generic: RwMutex<ProtocolSocketTable<GenlMessage>>,
+    audit: RwMutex<ProtocolSocketTable<AuditMessage>>,
 }
 
 impl NetlinkSocketTable {
@@ -32,6 +35,8 @@ impl NetlinkSocketTable {
         Self {
             route: RwMutex::new(ProtocolSocketTable::new()),
             uevent: RwMutex::new(ProtocolSocketTable::new()),
+            generic: RwMutex::new(ProtocolSocketTable::new()),
+            audit: RwMutex::new(ProtocolSocketTable::new()),
         }
     }
 }
@@ -41,6 +46,11 @@ pub trait SupportedNetlinkProtocol {
 
     fn socket_table() -> &'static RwMutex<ProtocolSocketTable<Self::Message>>;
 
+    /// Checks whether the current thread can join the multicast groups.
+    fn check_groups(_groups: GroupIdSet) -> Result<()> {
+        Ok(())
+    }
+
     fn bind(
         addr: &NetlinkSocketAddr,
         receiver: MessageReceiver<Self::Message>,
@@ -57,7 +67,6 @@ pub trait SupportedNetlinkProtocol {
         socket_table.unicast(dst_port, message)
     }
 
-    #[cfg_attr(not(ktest), expect(dead_code))]
     fn multicast(dst_groups: GroupIdSet, message: Self::Message) -> Result<()>
     where
         Self::Message: MulticastMessage,
@@ -87,6 +96,44 @@ impl SupportedNetlinkProtocol for NetlinkUeventProtocol {
     }
 }
 
+pub enum NetlinkGenericProtocol {}
+
+impl SupportedNetlinkProtocol for NetlinkGenericProtocol {
+    type Message = GenlMessage;
+
+    fn socket_table() -> &'static RwMutex<ProtocolSocketTable<Self::Message>> {
+        &NETLINK_SOCKET_TABLE.get().unwrap().generic
+    }
+}
+
+pub enum NetlinkAuditProtocol {}
+
+impl SupportedNetlinkProtocol for NetlinkAuditProtocol {
+    type Message = AuditMessage;
+
+    fn socket_table() -> &'static RwMutex<ProtocolSocketTable<Self::Message>> {
+        &NETLINK_SOCKET_TABLE.get().unwrap().audit
+    }
+
+    fn check_groups(groups: GroupIdSet) -> Result<()> {
+        // Reference: <https://elixir.bootlin.com/linux/v6.18/source/kernel/audit.c#L1695>.
+        if groups.is_empty() {
+            return Ok(());
+        }
+
+        let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+        let capset = credentials.effective_capset();
+        if !capset.contains(CapSet::AUDIT_READ) && !capset.contains(CapSet::SYS_ADMIN) {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "joining the audit multicast group requires the CAP_AUDIT_READ capability"
+            );
+        }
+
+        Ok(())
+    }
+}
+
 /// Bound socket table of a single netlink protocol.
 ///
 /// Each table can have bound sockets for unicast
@@ -148,6 +195,11 @@ impl<Message: 'static> ProtocolSocketTable<Message> {
         Ok(BoundHandle::new(socket_table, port, addr.groups()))
     }
 
+    /// Returns whether a socket is bound to the port.
+    pub(super) fn contains_port(&self, port: PortNum) -> bool {
+        self.unicast_sockets.contains_key(&port)
+    }
+
     fn unicast(&self, dst_port: PortNum, message: Message) -> Result<()>
     where
         Message: QueueableMessage,
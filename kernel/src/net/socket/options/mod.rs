@@ -2,8 +2,8 @@
 
 use macros::impl_socket_options;
 
-use super::util::LingerOption;
-use crate::{net::socket::unix::CUserCred, prelude::*, process::Gid};
+use super::util::{LingerOption, SocketFilter, TimestampingFlags};
+use crate::{bpf::BpfProg, net::socket::unix::CUserCred, prelude::*, process::Gid};
 
 pub(in crate::net) mod macros;
 
@@ -30,4 +30,13 @@ impl_socket_options!(
     pub struct SendBufForce(u32);
     pub struct RecvBufForce(u32);
     pub struct PeerGroups(Arc<[Gid]>);
+    pub struct AttachFilter(SocketFilter);
+    pub struct DetachFilter(i32);
+    pub struct AttachBpf(Arc<BpfProg>);
+    pub struct LockFilter(bool);
+    pub struct Zerocopy(bool);
+    pub struct Timestamp(bool);
+    pub struct TimestampNs(bool);
+    pub struct Timestamping(TimestampingFlags);
+    pub struct BindToDevice(String);
 );
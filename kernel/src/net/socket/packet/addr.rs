@@ -0,0 +1,42 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{net::socket::util::SocketAddr, prelude::*};
+
+/// The socket address of a packet socket.
+///
+/// This corresponds to `struct sockaddr_ll` in Linux.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PacketSocketAddr {
+    /// The protocol in host byte order (e.g., `ETH_P_IP`).
+    pub protocol: u16,
+    /// The index of the iface, where zero means any iface.
+    pub ifindex: u32,
+    /// The hardware type of the iface (e.g., `ARPHRD_ETHER`).
+    pub hatype: u16,
+    /// The packet type (e.g., `PACKET_HOST`).
+    pub pkttype: u8,
+    /// The length of the hardware address.
+    pub halen: u8,
+    /// The hardware address.
+    pub addr: [u8; 8],
+}
+
+impl TryFrom<SocketAddr> for PacketSocketAddr {
+    type Error = Error;
+
+    fn try_from(value: SocketAddr) -> Result<Self> {
+        match value {
+            SocketAddr::Packet(addr) => Ok(addr),
+            _ => return_errno_with_message!(
+                Errno::EINVAL,
+                "the address is in an unsupported address family"
+            ),
+        }
+    }
+}
+
+impl From<PacketSocketAddr> for SocketAddr {
+    fn from(value: PacketSocketAddr) -> Self {
+        SocketAddr::Packet(value)
+    }
+}
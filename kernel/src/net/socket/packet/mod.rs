@@ -0,0 +1,44 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! This module defines packet sockets.
+//!
+//! Packet sockets send and receive frames at the link layer. They are used by programs that need
+//! to see the link-layer traffic (e.g., tcpdump) or to handle link-layer protocols themselves
+//! (e.g., DHCP clients).
+//!
+//! A packet socket receives the frames of its protocol that are received or sent on all the ifaces,
+//! or on one iface if it is bound to the iface. The frames can be filtered by a socket filter
+//! attached with `SO_ATTACH_FILTER`. Instead of the receive queue, the frames can also be placed in
+//! a receive ring mapped into the user space, which is set up with `PACKET_RX_RING`.
+
+mod addr;
+pub mod options;
+mod receiver;
+mod ring;
+mod socket;
+
+pub use addr::PacketSocketAddr;
+pub(super) use receiver::PACKET_RECV_BUF_LEN;
+pub(in crate::net) use receiver::PacketObserver;
+pub use ring::CTpacketReq3;
+pub use socket::PacketSocket;
+
+/// The type of a [`PacketSocket`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PacketSocketType {
+    /// A `SOCK_RAW` packet socket that sees the link-layer headers.
+    Raw,
+    /// A `SOCK_DGRAM` packet socket that does not see the link-layer headers.
+    Dgram,
+}
+
+/// The statistics of a [`PacketSocket`].
+#[derive(Debug, Clone, Copy, Default)]
+struct PacketStats {
+    /// The number of packets received.
+    packets: u32,
+    /// The number of packets dropped because the receive queue or the receive ring is full.
+    drops: u32,
+    /// The number of times that the receive ring is frozen.
+    freeze_q_cnt: u32,
+}
@@ -0,0 +1,46 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::ring::CTpacketReq3;
+use crate::{net::socket::options::macros::impl_socket_options, prelude::*};
+
+impl_socket_options!(
+    pub struct PacketAddMembership(CPacketMreq);
+    pub struct PacketDropMembership(CPacketMreq);
+    pub struct PacketRxRing(CTpacketReq3);
+    pub struct PacketStatistics(CTpacketStats);
+    pub struct PacketAuxdata(bool);
+    pub struct PacketVersion(u32);
+    pub struct PacketHdrLen(());
+    pub struct PacketReserve(u32);
+);
+
+/// The membership request for `PACKET_ADD_MEMBERSHIP` and `PACKET_DROP_MEMBERSHIP`.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/if_packet.h#L292>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Pod)]
+pub struct CPacketMreq {
+    /// Interface index.
+    pub mr_ifindex: i32,
+    /// Membership type (e.g., `PACKET_MR_PROMISC`).
+    pub mr_type: u16,
+    /// Length of the address.
+    pub mr_alen: u16,
+    /// Physical-layer address.
+    pub mr_address: [u8; 8],
+}
+
+/// The statistics returned by `PACKET_STATISTICS`.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/if_packet.h#L43>.
+#[derive(Debug, Clone, Copy)]
+pub struct CTpacketStats {
+    /// The number of packets received, including the dropped ones.
+    pub tp_packets: u32,
+    /// The number of packets dropped.
+    pub tp_drops: u32,
+    /// The number of times that the receive ring is frozen.
+    ///
+    /// This is only reported for `TPACKET_V3`.
+    pub tp_freeze_q_cnt: Option<u32>,
+}
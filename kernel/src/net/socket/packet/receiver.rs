@@ -0,0 +1,295 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_bigtcp::{
+    socket::{FrameObserver, FrameType, SocketEventObserver, SocketEvents},
+    wire::ETHERNET_HEADER_LEN,
+};
+use aster_softirq::BottomHalfDisabled;
+
+use super::{PacketSocketAddr, PacketSocketType, PacketStats, ring::RxRing};
+use crate::{
+    events::IoEvents,
+    net::socket::util::{FilterMetadata, SocketFilter},
+    prelude::*,
+    process::signal::Pollee,
+};
+
+/// The receiving side of a packet socket.
+///
+/// The receiver is shared by the observers on all the ifaces, so that a packet socket can receive
+/// the frames from any iface.
+pub(super) struct PacketReceiver {
+    inner: SpinLock<ReceiverInner, BottomHalfDisabled>,
+    socket_type: PacketSocketType,
+    pollee: Pollee,
+}
+
+struct ReceiverInner {
+    /// The protocol in host byte order, where zero means that no frames are received.
+    protocol: u16,
+    /// The index of the bound iface, where zero means any iface.
+    ifindex: u32,
+    filter: Option<SocketFilter>,
+    queue: VecDeque<ReceivedFrame>,
+    queue_len: usize,
+    recv_buf: usize,
+    ring: Option<RxRing>,
+    stats: PacketStats,
+}
+
+/// A frame in the receive queue.
+#[derive(Clone)]
+pub(super) struct ReceivedFrame {
+    /// The data seen by the socket, which has been truncated by the socket filter.
+    pub(super) data: Vec<u8>,
+    pub(super) addr: PacketSocketAddr,
+}
+
+impl PacketReceiver {
+    pub(super) fn new(
+        socket_type: PacketSocketType,
+        protocol: u16,
+        recv_buf: u32,
+        pollee: Pollee,
+    ) -> Self {
+        let inner = ReceiverInner {
+            protocol,
+            ifindex: 0,
+            filter: None,
+            queue: VecDeque::new(),
+            queue_len: 0,
+            recv_buf: recv_buf as usize,
+            ring: None,
+            stats: PacketStats::default(),
+        };
+
+        Self {
+            inner: SpinLock::new(inner),
+            socket_type,
+            pollee,
+        }
+    }
+
+    /// Returns the protocol and the index of the bound iface.
+    pub(super) fn binding(&self) -> (u16, u32) {
+        let inner = self.inner.lock();
+        (inner.protocol, inner.ifindex)
+    }
+
+    /// Binds the receiver to the protocol and the iface.
+    ///
+    /// If `protocol` is zero, the protocol is not changed.
+    pub(super) fn bind(&self, protocol: u16, ifindex: u32) {
+        let mut inner = self.inner.lock();
+        if protocol != 0 {
+            inner.protocol = protocol;
+        }
+        inner.ifindex = ifindex;
+    }
+
+    pub(super) fn set_filter(&self, filter: Option<SocketFilter>) -> Option<SocketFilter> {
+        core::mem::replace(&mut self.inner.lock().filter, filter)
+    }
+
+    pub(super) fn set_recv_buf(&self, recv_buf: u32) {
+        self.inner.lock().recv_buf = recv_buf as usize;
+    }
+
+    /// Takes the statistics, which are reset afterwards.
+    pub(super) fn take_stats(&self) -> PacketStats {
+        core::mem::take(&mut self.inner.lock().stats)
+    }
+
+    /// Installs the receive ring, or removes the receive ring if `ring` is `None`.
+    ///
+    /// This method returns the old receive ring, which should be dropped after the lock is
+    /// released.
+    pub(super) fn set_ring(&self, ring: Option<RxRing>) -> Option<RxRing> {
+        core::mem::replace(&mut self.inner.lock().ring, ring)
+    }
+
+    /// Calls `f` with the receive ring.
+    ///
+    /// This method returns `None` if there is no receive ring.
+    pub(super) fn with_ring<F, R>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce(&RxRing) -> R,
+    {
+        self.inner.lock().ring.as_ref().map(f)
+    }
+
+    /// Receives a frame from the receive queue.
+    ///
+    /// If `is_peek` is true, the frame is kept in the queue.
+    pub(super) fn recv(&self, is_peek: bool) -> Result<ReceivedFrame> {
+        let mut inner = self.inner.lock();
+
+        let frame = if is_peek {
+            inner.queue.front().cloned()
+        } else {
+            inner.queue.pop_front()
+        };
+        let Some(frame) = frame else {
+            return_errno_with_message!(Errno::EAGAIN, "the receive queue is empty");
+        };
+
+        if !is_peek {
+            inner.queue_len -= frame.data.len();
+        }
+
+        Ok(frame)
+    }
+
+    /// Retires the current block of the receive ring.
+    ///
+    /// This method is called periodically by the retire timer.
+    pub(super) fn on_retire_timer(&self) {
+        let mut inner_guard = self.inner.lock();
+        let inner = &mut *inner_guard;
+
+        let Some(ring) = inner.ring.as_mut() else {
+            return;
+        };
+        ring.on_retire_timer(&mut inner.stats);
+        let has_user_blocks = ring.has_user_blocks();
+
+        drop(inner_guard);
+        if has_user_blocks {
+            self.pollee.notify(IoEvents::IN);
+        }
+    }
+
+    pub(super) fn check_io_events(&self) -> IoEvents {
+        let inner = self.inner.lock();
+
+        let can_recv = match inner.ring.as_ref() {
+            Some(ring) => ring.has_user_blocks(),
+            None => !inner.queue.is_empty(),
+        };
+
+        if can_recv {
+            IoEvents::IN
+        } else {
+            IoEvents::empty()
+        }
+    }
+
+    fn on_frame(&self, frame: &[u8], frame_type: FrameType, ifindex: u32, hatype: u16) {
+        let protocol = u16::from_be_bytes([frame[12], frame[13]]);
+        let pkttype = match frame_type {
+            FrameType::Host => PACKET_HOST,
+            FrameType::Broadcast => PACKET_BROADCAST,
+            FrameType::Multicast => PACKET_MULTICAST,
+            FrameType::Outgoing => PACKET_OUTGOING,
+        };
+
+        let mut inner_guard = self.inner.lock();
+        let inner = &mut *inner_guard;
+
+        if inner.protocol != ETH_P_ALL && inner.protocol != protocol {
+            return;
+        }
+        if inner.ifindex != 0 && inner.ifindex != ifindex {
+            return;
+        }
+
+        let data_offset = match self.socket_type {
+            PacketSocketType::Raw => 0,
+            PacketSocketType::Dgram => ETHERNET_HEADER_LEN,
+        };
+        let data_len = frame.len() - data_offset;
+
+        let snaplen = match inner.filter.as_ref() {
+            Some(filter) => {
+                let metadata = FilterMetadata {
+                    protocol,
+                    pkttype,
+                    ifindex,
+                    hatype,
+                };
+                (filter.run(frame, data_offset, &metadata) as usize).min(data_len)
+            }
+            None => data_len,
+        };
+        if snaplen == 0 {
+            return;
+        }
+
+        let mut addr = PacketSocketAddr {
+            protocol,
+            ifindex,
+            hatype,
+            pkttype,
+            halen: 6,
+            addr: [0; 8],
+        };
+        addr.addr[..6].copy_from_slice(&frame[6..12]);
+
+        let is_received = if let Some(ring) = inner.ring.as_mut() {
+            ring.write_frame(frame, data_offset, snaplen, &addr, &mut inner.stats)
+        } else if inner.queue_len >= inner.recv_buf {
+            false
+        } else {
+            let data = frame[data_offset..data_offset + snaplen].to_vec();
+            inner.queue_len += data.len();
+            inner.queue.push_back(ReceivedFrame { data, addr });
+            true
+        };
+
+        if !is_received {
+            inner.stats.drops += 1;
+            return;
+        }
+        inner.stats.packets += 1;
+
+        drop(inner_guard);
+        self.pollee.notify(IoEvents::IN);
+    }
+}
+
+/// An observer that passes the frames on an iface to a [`PacketReceiver`].
+pub struct PacketObserver {
+    receiver: Arc<PacketReceiver>,
+    ifindex: u32,
+    hatype: u16,
+}
+
+impl PacketObserver {
+    pub(super) fn new(receiver: Arc<PacketReceiver>, ifindex: u32, hatype: u16) -> Self {
+        Self {
+            receiver,
+            ifindex,
+            hatype,
+        }
+    }
+}
+
+impl SocketEventObserver for PacketObserver {
+    fn on_events(&self, events: SocketEvents) {
+        if events.contains(SocketEvents::CAN_SEND) {
+            self.receiver.pollee.notify(IoEvents::OUT);
+        }
+    }
+}
+
+impl FrameObserver for PacketObserver {
+    fn on_frame(&self, frame: &[u8], frame_type: FrameType) {
+        self.receiver
+            .on_frame(frame, frame_type, self.ifindex, self.hatype);
+    }
+}
+
+/// The default receive buffer size of packet sockets.
+///
+/// This is the default value of `net.core.rmem_default` in Linux.
+pub const PACKET_RECV_BUF_LEN: usize = 212992;
+
+/// The protocol number that matches all the protocols.
+const ETH_P_ALL: u16 = 0x0003;
+
+// Packet types.
+// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/if_packet.h#L26>.
+const PACKET_HOST: u8 = 0;
+const PACKET_BROADCAST: u8 = 1;
+const PACKET_MULTICAST: u8 = 2;
+const PACKET_OUTGOING: u8 = 4;
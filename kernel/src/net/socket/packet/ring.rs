@@ -0,0 +1,454 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The receive ring of packet sockets (`PACKET_RX_RING`).
+//!
+//! Only the block-based layout of `TPACKET_V3` is supported. The ring consists of blocks, each of
+//! which contains a block descriptor followed by packets. The kernel fills the blocks one by one
+//! and hands a block over to userspace by setting its status to `TP_STATUS_USER`. Userspace
+//! returns the block to the kernel by setting its status back to `TP_STATUS_KERNEL`.
+//!
+//! Reference: <https://www.kernel.org/doc/html/v6.0/networking/packet_mmap.html>.
+
+use core::{
+    sync::atomic::{Ordering, fence},
+    time::Duration,
+};
+
+use align_ext::AlignExt;
+use aster_bigtcp::wire::ETHERNET_HEADER_LEN;
+use ostd::mm::VmIo;
+
+use super::{PacketSocketAddr, PacketSocketType, PacketStats};
+use crate::{
+    prelude::*,
+    time::clocks::RealTimeClock,
+    util::net::CSocketAddrLl,
+    vm::vmo::{CommitFlags, Vmo, VmoOptions},
+};
+
+/// The ring request for `PACKET_RX_RING`.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/if_packet.h#L274>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub struct CTpacketReq3 {
+    /// Minimal size of contiguous block.
+    tp_block_size: u32,
+    /// Number of blocks.
+    tp_block_nr: u32,
+    /// Size of frame.
+    tp_frame_size: u32,
+    /// Total number of frames.
+    tp_frame_nr: u32,
+    /// Timeout in milliseconds.
+    tp_retire_blk_tov: u32,
+    /// Offset to private data area.
+    tp_sizeof_priv: u32,
+    /// Feature request word.
+    tp_feature_req_word: u32,
+}
+
+impl CTpacketReq3 {
+    /// Returns whether the request asks to tear down the ring.
+    pub(super) fn is_teardown(&self) -> bool {
+        self.tp_block_nr == 0
+    }
+
+    /// Checks whether the teardown request is valid.
+    pub(super) fn check_teardown(&self) -> Result<()> {
+        if self.tp_frame_nr != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the number of frames must be zero");
+        }
+        Ok(())
+    }
+}
+
+/// A `TPACKET_V3` receive ring.
+pub(super) struct RxRing {
+    vmo: Arc<Vmo>,
+    socket_type: PacketSocketType,
+    block_size: usize,
+    block_nr: usize,
+    /// The offset of the first packet in a block.
+    first_pkt_offset: usize,
+    /// The number of bytes reserved before the link-layer header.
+    reserve: usize,
+    /// The timeout to retire a non-empty block.
+    retire_tov: Duration,
+
+    /// The index of the current block.
+    current: usize,
+    /// The offset of the next packet in the current block.
+    next_offset: usize,
+    /// The offset of the last packet in the current block.
+    last_offset: usize,
+    /// The number of packets in the current block.
+    num_pkts: u32,
+    /// The sequence number of the next block.
+    next_seq_num: u64,
+    /// Whether the ring is frozen because userspace has not released the current block.
+    is_frozen: bool,
+}
+
+impl RxRing {
+    /// Creates a receive ring according to the request.
+    ///
+    /// Like Linux, this method fails with `EINVAL` if the request is not valid.
+    pub(super) fn new(
+        req: &CTpacketReq3,
+        socket_type: PacketSocketType,
+        reserve: u32,
+    ) -> Result<Self> {
+        let block_size = req.tp_block_size as usize;
+        let block_nr = req.tp_block_nr as usize;
+        let frame_size = req.tp_frame_size as usize;
+        let reserve = reserve as usize;
+
+        if block_size == 0 || block_size > i32::MAX as usize || block_size % PAGE_SIZE != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the block size is invalid");
+        }
+
+        let first_pkt_offset = BLOCK_DESC_LEN + (req.tp_sizeof_priv as usize).align_up(8);
+        let min_frame_size = TPACKET3_HDRLEN + reserve;
+        if block_size < first_pkt_offset + min_frame_size {
+            return_errno_with_message!(Errno::EINVAL, "the block size is too small");
+        }
+        if frame_size < min_frame_size || frame_size % TPACKET_ALIGNMENT != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the frame size is invalid");
+        }
+
+        let frames_per_block = block_size / frame_size;
+        if frames_per_block == 0
+            || frames_per_block.checked_mul(block_nr) != Some(req.tp_frame_nr as usize)
+        {
+            return_errno_with_message!(Errno::EINVAL, "the number of frames is invalid");
+        }
+
+        let ring_size = block_size
+            .checked_mul(block_nr)
+            .filter(|size| *size <= u32::MAX as usize)
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "the ring is too large"))?;
+
+        // The ring is written when packets arrive, where we cannot sleep to commit pages. So all
+        // the pages are committed in advance.
+        let vmo = VmoOptions::new(ring_size).alloc()?;
+        for page_idx in 0..ring_size / PAGE_SIZE {
+            vmo.commit_on(page_idx, CommitFlags::empty())?;
+        }
+
+        let retire_tov = if req.tp_retire_blk_tov == 0 {
+            DEFAULT_RETIRE_TOV
+        } else {
+            Duration::from_millis(req.tp_retire_blk_tov as u64)
+        };
+
+        let mut ring = Self {
+            vmo,
+            socket_type,
+            block_size,
+            block_nr,
+            first_pkt_offset,
+            reserve,
+            retire_tov,
+            current: 0,
+            next_offset: first_pkt_offset,
+            last_offset: first_pkt_offset,
+            num_pkts: 0,
+            next_seq_num: 1,
+            is_frozen: false,
+        };
+        ring.open_block();
+
+        Ok(ring)
+    }
+
+    /// Returns the VMO that backs the ring.
+    pub(super) fn vmo(&self) -> &Arc<Vmo> {
+        &self.vmo
+    }
+
+    /// Returns the timeout to retire a non-empty block.
+    pub(super) fn retire_tov(&self) -> Duration {
+        self.retire_tov
+    }
+
+    /// Returns whether there are blocks that userspace can consume.
+    pub(super) fn has_user_blocks(&self) -> bool {
+        let prev = (self.current + self.block_nr - 1) % self.block_nr;
+        self.block_status(prev) != TP_STATUS_KERNEL
+    }
+
+    /// Writes a frame to the ring.
+    ///
+    /// The data seen by the socket starts at `data_offset` in the frame, and only the first
+    /// `snaplen` bytes of the data are written. This method returns `false` if the frame is
+    /// dropped because the ring is full.
+    pub(super) fn write_frame(
+        &mut self,
+        frame: &[u8],
+        data_offset: usize,
+        snaplen: usize,
+        addr: &PacketSocketAddr,
+        stats: &mut PacketStats,
+    ) -> bool {
+        let data = &frame[data_offset..];
+
+        let (mut mac_offset, net_offset) = match self.socket_type {
+            PacketSocketType::Raw => {
+                let net_offset = (TPACKET3_HDRLEN + ETHERNET_HEADER_LEN.max(16))
+                    .align_up(TPACKET_ALIGNMENT)
+                    + self.reserve;
+                (net_offset - ETHERNET_HEADER_LEN, net_offset)
+            }
+            PacketSocketType::Dgram => {
+                let net_offset = TPACKET3_HDRLEN.align_up(TPACKET_ALIGNMENT) + 16 + self.reserve;
+                (net_offset, net_offset)
+            }
+        };
+
+        let max_frame_len = self.block_size - self.first_pkt_offset;
+        let mut snaplen = snaplen.min(data.len());
+        if mac_offset + snaplen > max_frame_len {
+            snaplen = max_frame_len.saturating_sub(mac_offset);
+            mac_offset = mac_offset.min(max_frame_len);
+        }
+
+        let pkt_len = (mac_offset + snaplen).align_up(8);
+        let Some(pkt_offset) = self.alloc_pkt(pkt_len, stats) else {
+            return false;
+        };
+        let pkt_start = self.current * self.block_size + pkt_offset;
+
+        let now = RealTimeClock::get().read_time();
+        let hdr = CTpacket3Hdr {
+            tp_next_offset: pkt_len as u32,
+            tp_sec: now.as_secs() as u32,
+            tp_nsec: now.subsec_nanos(),
+            tp_snaplen: snaplen as u32,
+            tp_len: data.len() as u32,
+            tp_status: TP_STATUS_USER,
+            tp_mac: mac_offset as u16,
+            tp_net: net_offset as u16,
+            ..CTpacket3Hdr::new_zeroed()
+        };
+        self.write_val(pkt_start, &hdr);
+        self.write_val(
+            pkt_start + size_of::<CTpacket3Hdr>().align_up(TPACKET_ALIGNMENT),
+            &CSocketAddrLl::from(*addr),
+        );
+        self.write_bytes(pkt_start + mac_offset, &data[..snaplen]);
+
+        true
+    }
+
+    /// Retires the current block if it is not empty, or thaws the ring if userspace has released
+    /// the current block.
+    ///
+    /// This method should be called periodically, so that userspace can see the packets even if
+    /// the current block is not full.
+    pub(super) fn on_retire_timer(&mut self, stats: &mut PacketStats) {
+        if self.is_frozen {
+            if self.block_status(self.current) == TP_STATUS_KERNEL {
+                self.open_block();
+            }
+            return;
+        }
+
+        if self.num_pkts == 0 {
+            return;
+        }
+
+        self.close_block(TP_STATUS_BLK_TMO, stats);
+        self.dispatch_next_block(stats);
+    }
+
+    /// Allocates space for a packet in the current block, and returns the offset of the packet in
+    /// the block.
+    fn alloc_pkt(&mut self, pkt_len: usize, stats: &mut PacketStats) -> Option<usize> {
+        if self.is_frozen {
+            if self.block_status(self.current) != TP_STATUS_KERNEL {
+                return None;
+            }
+            self.open_block();
+        }
+
+        if self.next_offset + pkt_len >= self.block_size {
+            // There is no room for the packet in the current block.
+            self.close_block(0, stats);
+            if !self.dispatch_next_block(stats) {
+                return None;
+            }
+        }
+
+        let offset = self.next_offset;
+        self.last_offset = offset;
+        self.next_offset += pkt_len;
+        self.num_pkts += 1;
+
+        Some(offset)
+    }
+
+    /// Opens the current block for the kernel to fill.
+    fn open_block(&mut self) {
+        let now = RealTimeClock::get().read_time();
+        let ts = CTpacketBdTs {
+            ts_sec: now.as_secs() as u32,
+            ts_nsec: now.subsec_nanos(),
+        };
+
+        let desc = CTpacketBlockDesc {
+            version: TPACKET_V3,
+            offset_to_priv: BLOCK_DESC_LEN as u32,
+            block_status: TP_STATUS_KERNEL,
+            num_pkts: 0,
+            offset_to_first_pkt: self.first_pkt_offset as u32,
+            blk_len: self.first_pkt_offset as u32,
+            seq_num: self.next_seq_num,
+            ts_first_pkt: ts,
+            ts_last_pkt: ts,
+        };
+        self.write_val(self.current * self.block_size, &desc);
+
+        self.next_seq_num += 1;
+        self.next_offset = self.first_pkt_offset;
+        self.last_offset = self.first_pkt_offset;
+        self.num_pkts = 0;
+        self.is_frozen = false;
+    }
+
+    /// Closes the current block and hands it over to userspace.
+    fn close_block(&mut self, status: u32, stats: &PacketStats) {
+        let block_start = self.current * self.block_size;
+
+        let ts_last_pkt = if self.num_pkts > 0 {
+            let last_start = block_start + self.last_offset;
+            // The last packet has no next packet.
+            self.write_val(last_start, &0u32);
+            let hdr = self.read_val::<CTpacket3Hdr>(last_start);
+            CTpacketBdTs {
+                ts_sec: hdr.tp_sec,
+                ts_nsec: hdr.tp_nsec,
+            }
+        } else {
+            let now = RealTimeClock::get().read_time();
+            CTpacketBdTs {
+                ts_sec: now.as_secs() as u32,
+                ts_nsec: now.subsec_nanos(),
+            }
+        };
+
+        self.write_val(block_start + BLOCK_NUM_PKTS_OFFSET, &self.num_pkts);
+        self.write_val(block_start + BLOCK_LEN_OFFSET, &(self.next_offset as u32));
+        self.write_val(block_start + BLOCK_TS_LAST_OFFSET, &ts_last_pkt);
+
+        let mut status = TP_STATUS_USER | status;
+        if stats.drops > 0 {
+            status |= TP_STATUS_LOSING;
+        }
+
+        // Userspace must see the packets before it sees the block status.
+        fence(Ordering::Release);
+        self.write_val(block_start + BLOCK_STATUS_OFFSET, &status);
+
+        self.current = (self.current + 1) % self.block_nr;
+    }
+
+    /// Opens the next block, or freezes the ring if userspace has not released the block.
+    fn dispatch_next_block(&mut self, stats: &mut PacketStats) -> bool {
+        if self.block_status(self.current) != TP_STATUS_KERNEL {
+            self.is_frozen = true;
+            stats.freeze_q_cnt += 1;
+            return false;
+        }
+
+        self.open_block();
+        true
+    }
+
+    fn block_status(&self, block: usize) -> u32 {
+        let status = self.read_val::<u32>(block * self.block_size + BLOCK_STATUS_OFFSET);
+        fence(Ordering::Acquire);
+        status
+    }
+
+    fn read_val<T: Pod>(&self, offset: usize) -> T {
+        // The pages are committed and the offset is in bounds, so this will not fail.
+        self.vmo.read_val(offset).unwrap()
+    }
+
+    fn write_val<T: Pod>(&self, offset: usize, val: &T) {
+        // The pages are committed and the offset is in bounds, so this will not fail.
+        self.vmo.write_val(offset, val).unwrap();
+    }
+
+    fn write_bytes(&self, offset: usize, bytes: &[u8]) {
+        // The pages are committed and the range is in bounds, so this will not fail.
+        self.vmo.write_bytes(offset, bytes).unwrap();
+    }
+}
+
+/// The block descriptor at the start of each block.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/if_packet.h#L241>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CTpacketBlockDesc {
+    version: u32,
+    offset_to_priv: u32,
+    block_status: u32,
+    num_pkts: u32,
+    offset_to_first_pkt: u32,
+    blk_len: u32,
+    seq_num: u64,
+    ts_first_pkt: CTpacketBdTs,
+    ts_last_pkt: CTpacketBdTs,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CTpacketBdTs {
+    ts_sec: u32,
+    ts_nsec: u32,
+}
+
+/// The header of each packet in the ring.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/if_packet.h#L185>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CTpacket3Hdr {
+    tp_next_offset: u32,
+    tp_sec: u32,
+    tp_nsec: u32,
+    tp_snaplen: u32,
+    tp_len: u32,
+    tp_status: u32,
+    tp_mac: u16,
+    tp_net: u16,
+    tp_rxhash: u32,
+    tp_vlan_tci: u32,
+    tp_vlan_tpid: u16,
+    tp_hv1_padding: u16,
+    tp_padding: [u8; 8],
+}
+
+const BLOCK_STATUS_OFFSET: usize = 8;
+const BLOCK_NUM_PKTS_OFFSET: usize = 12;
+const BLOCK_LEN_OFFSET: usize = 20;
+const BLOCK_TS_LAST_OFFSET: usize = 40;
+const BLOCK_DESC_LEN: usize = size_of::<CTpacketBlockDesc>();
+
+const TPACKET_ALIGNMENT: usize = 16;
+/// The length of the packet header followed by the link-layer socket address.
+const TPACKET3_HDRLEN: usize = size_of::<CTpacket3Hdr>() + size_of::<CSocketAddrLl>();
+
+/// The default timeout to retire a non-empty block.
+const DEFAULT_RETIRE_TOV: Duration = Duration::from_millis(8);
+
+const TP_STATUS_KERNEL: u32 = 0;
+const TP_STATUS_USER: u32 = 1 << 0;
+const TP_STATUS_LOSING: u32 = 1 << 2;
+const TP_STATUS_BLK_TMO: u32 = 1 << 5;
+
+/// The version number of `TPACKET_V3`.
+pub(super) const TPACKET_V3: u32 = 2;
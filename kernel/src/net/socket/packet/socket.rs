@@ -0,0 +1,556 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use aster_bigtcp::{errors::packet::SendError, wire::ETHERNET_HEADER_LEN};
+
+use super::{
+    PacketSocketAddr, PacketSocketType,
+    options::{
+        CPacketMreq, CTpacketStats, PacketAddMembership, PacketAuxdata, PacketDropMembership,
+        PacketHdrLen, PacketReserve, PacketRxRing, PacketStatistics, PacketVersion,
+    },
+    receiver::{PacketObserver, PacketReceiver},
+    ring::{CTpacketReq3, RxRing, TPACKET_V3},
+};
+use crate::{
+    events::IoEvents,
+    fs::{file::Mappable, pseudofs::SockFs, vfs::path::Path},
+    net::{
+        iface::{self, iter_all_ifaces},
+        socket::{
+            Socket,
+            options::{
+                AttachBpf, AttachFilter, DetachFilter, Error as SocketError, SocketOption,
+                macros::{sock_option_mut, sock_option_ref},
+            },
+            private::SocketPrivate,
+            util::{
+                MessageHeader, SendRecvFlags, SocketAddr, SocketFilter,
+                options::{GetSocketLevelOption, SetSocketLevelOption, SocketOptionSet},
+            },
+        },
+    },
+    prelude::*,
+    process::{
+        credentials::capabilities::CapSet,
+        posix_thread::AsPosixThread,
+        signal::{PollHandle, Pollable, Pollee},
+    },
+    time::{Timer, clocks::MonotonicClock, timer::Timeout},
+    util::{MultiRead, MultiWrite},
+};
+
+/// A packet socket (`AF_PACKET`).
+///
+/// A `SOCK_RAW` packet socket sends and receives whole frames, while a `SOCK_DGRAM` packet socket
+/// sends and receives frames without link-layer headers.
+pub struct PacketSocket {
+    socket_type: PacketSocketType,
+    /// The packet sockets attached to all the ifaces.
+    sockets: Vec<iface::PacketSocket>,
+    receiver: Arc<PacketReceiver>,
+    // Lock order: `ring_timer` first, `options` second
+    /// The timer to retire the blocks in the receive ring.
+    ///
+    /// The timer exists if and only if the receive ring exists, so the lock also serializes the
+    /// setup of the receive ring.
+    ring_timer: Mutex<Option<Arc<Timer>>>,
+    options: RwLock<OptionSet>,
+
+    is_nonblocking: AtomicBool,
+    pollee: Pollee,
+    pseudo_path: Path,
+}
+
+#[derive(Debug, Clone)]
+struct OptionSet {
+    socket: SocketOptionSet,
+    version: u32,
+    reserve: u32,
+    auxdata: bool,
+    memberships: Vec<CPacketMreq>,
+}
+
+impl OptionSet {
+    fn new() -> Self {
+        Self {
+            socket: SocketOptionSet::new_packet(),
+            version: TPACKET_V1,
+            reserve: 0,
+            auxdata: false,
+            memberships: Vec::new(),
+        }
+    }
+}
+
+/// The version number of `TPACKET_V1`, which is the default version.
+const TPACKET_V1: u32 = 0;
+
+impl PacketSocket {
+    /// Creates a `SOCK_RAW` packet socket for the protocol in host byte order.
+    ///
+    /// Like Linux, this fails with `EPERM` if the current process does not have `CAP_NET_RAW`.
+    pub fn new_raw(is_nonblocking: bool, protocol: u16) -> Result<Arc<Self>> {
+        Self::new(is_nonblocking, PacketSocketType::Raw, protocol)
+    }
+
+    /// Creates a `SOCK_DGRAM` packet socket for the protocol in host byte order.
+    ///
+    /// Like Linux, this fails with `EPERM` if the current process does not have `CAP_NET_RAW`.
+    pub fn new_dgram(is_nonblocking: bool, protocol: u16) -> Result<Arc<Self>> {
+        Self::new(is_nonblocking, PacketSocketType::Dgram, protocol)
+    }
+
+    fn new(
+        is_nonblocking: bool,
+        socket_type: PacketSocketType,
+        protocol: u16,
+    ) -> Result<Arc<Self>> {
+        let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+        if !credentials.effective_capset().contains(CapSet::NET_RAW) {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "creating packet sockets requires the CAP_NET_RAW capability"
+            );
+        }
+
+        let options = OptionSet::new();
+        let pollee = Pollee::new();
+
+        let receiver = Arc::new(PacketReceiver::new(
+            socket_type,
+            protocol,
+            options.socket.recv_buf(),
+            pollee.clone(),
+        ));
+        let sockets = iter_all_ifaces()
+            .map(|iface| {
+                let observer =
+                    PacketObserver::new(receiver.clone(), iface.index(), iface.type_() as u16);
+                iface::PacketSocket::new(iface, observer)
+            })
+            .collect();
+
+        Ok(Arc::new(Self {
+            socket_type,
+            sockets,
+            receiver,
+            ring_timer: Mutex::new(None),
+            options: RwLock::new(options),
+            is_nonblocking: AtomicBool::new(is_nonblocking),
+            pollee,
+            pseudo_path: SockFs::new_path(),
+        }))
+    }
+
+    /// Finds the packet socket attached to the iface with the index.
+    fn find_socket(&self, ifindex: u32) -> Option<&iface::PacketSocket> {
+        self.sockets
+            .iter()
+            .find(|socket| socket.iface().index() == ifindex)
+    }
+
+    fn try_recv(
+        &self,
+        writer: &mut dyn MultiWrite,
+        flags: SendRecvFlags,
+    ) -> Result<(usize, PacketSocketAddr)> {
+        let frame = self
+            .receiver
+            .recv(flags.contains(SendRecvFlags::MSG_PEEK))?;
+        self.pollee.invalidate();
+
+        let copied_bytes = writer.write(&mut VmReader::from(frame.data.as_slice()))?;
+
+        // Like Linux, the real length of the frame is returned if `MSG_TRUNC` is specified.
+        let recv_bytes = if flags.contains(SendRecvFlags::MSG_TRUNC) {
+            frame.data.len()
+        } else {
+            copied_bytes
+        };
+
+        Ok((recv_bytes, frame.addr))
+    }
+
+    fn try_send(
+        &self,
+        reader: &mut dyn MultiRead,
+        remote: Option<&PacketSocketAddr>,
+    ) -> Result<usize> {
+        let (protocol, ifindex, dst_addr) = match remote {
+            Some(remote) => (remote.protocol, remote.ifindex, remote.addr),
+            None => {
+                let (protocol, ifindex) = self.receiver.binding();
+                (protocol, ifindex, [0; 8])
+            }
+        };
+
+        let socket = self.find_socket(ifindex).ok_or_else(|| {
+            Error::with_message(Errno::ENXIO, "the iface to send the frame does not exist")
+        })?;
+
+        let len = reader.sum_lens();
+
+        let mut frame = match self.socket_type {
+            PacketSocketType::Raw => Vec::with_capacity(len),
+            PacketSocketType::Dgram => {
+                let src_addr = socket.iface().ether_addr().map_or([0; 6], |addr| addr.0);
+
+                let mut frame = Vec::with_capacity(ETHERNET_HEADER_LEN + len);
+                frame.extend_from_slice(&dst_addr[..6]);
+                frame.extend_from_slice(&src_addr);
+                frame.extend_from_slice(&protocol.to_be_bytes());
+                frame
+            }
+        };
+        let header_len = frame.len();
+        frame.resize(header_len + len, 0);
+        reader.read(&mut VmWriter::from(&mut frame[header_len..]))?;
+
+        match socket.send(frame) {
+            Ok(()) => (),
+            Err(SendError::TooLarge) => {
+                return_errno_with_message!(Errno::EMSGSIZE, "the frame is too large");
+            }
+            Err(SendError::InvalidHeader) => {
+                return_errno_with_message!(Errno::EINVAL, "the frame is too short");
+            }
+            Err(SendError::BufferFull) => {
+                return_errno_with_message!(Errno::EAGAIN, "the send buffer is full");
+            }
+        }
+
+        self.pollee.invalidate();
+        socket.iface().poll();
+
+        Ok(len)
+    }
+
+    fn check_io_events(&self) -> IoEvents {
+        let mut events = self.receiver.check_io_events();
+
+        if self.sockets.iter().all(|socket| socket.can_send()) {
+            events |= IoEvents::OUT;
+        }
+
+        events
+    }
+
+    /// Sets up or tears down the receive ring.
+    fn set_rx_ring(&self, req: &CTpacketReq3) -> Result<()> {
+        let mut ring_timer = self.ring_timer.lock();
+
+        if req.is_teardown() {
+            req.check_teardown()?;
+            // Dropping the timer stops it.
+            *ring_timer = None;
+            self.receiver.set_ring(None);
+            return Ok(());
+        }
+
+        if ring_timer.is_some() {
+            return_errno_with_message!(Errno::EBUSY, "the receive ring already exists");
+        }
+
+        let (version, reserve) = {
+            let options = self.options.read();
+            (options.version, options.reserve)
+        };
+        if version != TPACKET_V3 {
+            // TODO: Support the frame-based layouts of `TPACKET_V1` and `TPACKET_V2`.
+            return_errno_with_message!(Errno::EINVAL, "only TPACKET_V3 rings are supported");
+        }
+
+        let ring = RxRing::new(req, self.socket_type, reserve)?;
+        let retire_tov = ring.retire_tov();
+        self.receiver.set_ring(Some(ring));
+
+        let receiver = Arc::downgrade(&self.receiver);
+        let timer = MonotonicClock::timer_manager().create_timer(move |_guard| {
+            if let Some(receiver) = receiver.upgrade() {
+                receiver.on_retire_timer();
+            }
+        });
+        {
+            let mut timer_guard = timer.lock();
+            timer_guard.set_interval(retire_tov);
+            timer_guard.set_timeout(Timeout::After(retire_tov));
+        }
+        *ring_timer = Some(timer);
+
+        Ok(())
+    }
+
+    /// Sets the option that cannot be changed once the receive ring exists.
+    fn set_ring_option<F>(&self, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut OptionSet),
+    {
+        let ring_timer = self.ring_timer.lock();
+        if ring_timer.is_some() {
+            return_errno_with_message!(
+                Errno::EBUSY,
+                "the option cannot be changed after the receive ring is set up"
+            );
+        }
+
+        f(&mut self.options.write());
+
+        Ok(())
+    }
+
+    fn check_membership(&self, mreq: &CPacketMreq) -> Result<()> {
+        if self.find_socket(mreq.mr_ifindex as u32).is_none() {
+            return_errno_with_message!(Errno::ENODEV, "the iface does not exist");
+        }
+        Ok(())
+    }
+}
+
+impl Pollable for PacketSocket {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.pollee
+            .poll_with(mask, poller, || self.check_io_events())
+    }
+}
+
+impl SocketPrivate for PacketSocket {
+    fn is_nonblocking(&self) -> bool {
+        self.is_nonblocking.load(Ordering::Relaxed)
+    }
+
+    fn set_nonblocking(&self, is_nonblocking: bool) {
+        self.is_nonblocking.store(is_nonblocking, Ordering::Relaxed);
+    }
+}
+
+impl Socket for PacketSocket {
+    fn bind(&self, socket_addr: SocketAddr) -> Result<()> {
+        let addr = PacketSocketAddr::try_from(socket_addr)?;
+
+        if addr.ifindex != 0 && self.find_socket(addr.ifindex).is_none() {
+            return_errno_with_message!(Errno::ENODEV, "the iface does not exist");
+        }
+
+        self.receiver.bind(addr.protocol, addr.ifindex);
+
+        Ok(())
+    }
+
+    fn addr(&self) -> Result<SocketAddr> {
+        let (protocol, ifindex) = self.receiver.binding();
+
+        let mut addr = PacketSocketAddr {
+            protocol,
+            ifindex,
+            hatype: 0,
+            pkttype: 0,
+            halen: 0,
+            addr: [0; 8],
+        };
+
+        if let Some(socket) = self.find_socket(ifindex) {
+            let iface = socket.iface();
+            addr.hatype = iface.type_() as u16;
+            addr.halen = 6;
+            if let Some(ether_addr) = iface.ether_addr() {
+                addr.addr[..6].copy_from_slice(ether_addr.as_bytes());
+            }
+        }
+
+        Ok(addr.into())
+    }
+
+    fn sendmsg(
+        &self,
+        reader: &mut dyn MultiRead,
+        message_header: MessageHeader,
+        flags: SendRecvFlags,
+    ) -> Result<usize> {
+        // TODO: Deal with flags
+        if !flags.is_all_supported() {
+            warn!("unsupported flags: {:?}", flags);
+        }
+
+        let MessageHeader {
+            addr,
+            control_messages,
+        } = message_header;
+
+        let remote = addr.map(PacketSocketAddr::try_from).transpose()?;
+
+        if !control_messages.is_empty() {
+            // TODO: Support sending control message
+            warn!("sending control message is not supported");
+        }
+
+        // TODO: Block if the send buffer is full
+        self.try_send(reader, remote.as_ref())
+    }
+
+    fn recvmsg(
+        &self,
+        writer: &mut dyn MultiWrite,
+        flags: SendRecvFlags,
+    ) -> Result<(usize, MessageHeader)> {
+        // TODO: Deal with other flags. Only MSG_PEEK and MSG_TRUNC are handled here.
+        if !flags
+            .sub(SendRecvFlags::MSG_PEEK | SendRecvFlags::MSG_TRUNC)
+            .is_all_supported()
+        {
+            warn!("unsupported flags: {:?}", flags);
+        }
+
+        let (received_bytes, peer_addr) =
+            self.block_on_with_flags(IoEvents::IN, flags, || self.try_recv(writer, flags))?;
+
+        // TODO: Receive control message (e.g., `PACKET_AUXDATA`)
+
+        let message_header = MessageHeader::new(Some(peer_addr.into()), Vec::new());
+
+        Ok((received_bytes, message_header))
+    }
+
+    fn get_option(&self, option: &mut dyn SocketOption) -> Result<()> {
+        sock_option_mut!(match option {
+            socket_errors @ SocketError => {
+                // TODO: Support socket errors for packet sockets
+                socket_errors.set(None);
+                return Ok(());
+            }
+            statistics @ PacketStatistics => {
+                let version = self.options.read().version;
+                let stats = self.receiver.take_stats();
+                // Like Linux, the dropped packets are counted as received packets.
+                statistics.set(CTpacketStats {
+                    tp_packets: stats.packets + stats.drops,
+                    tp_drops: stats.drops,
+                    tp_freeze_q_cnt: (version == TPACKET_V3).then_some(stats.freeze_q_cnt),
+                });
+                return Ok(());
+            }
+            _hdrlen @ PacketHdrLen => {
+                // The header length depends on the version specified by the user, so it is
+                // computed when the option is written to the user space.
+                return Ok(());
+            }
+            _ => (),
+        });
+
+        let options = self.options.read();
+
+        sock_option_mut!(match option {
+            version @ PacketVersion => {
+                version.set(options.version);
+                return Ok(());
+            }
+            reserve @ PacketReserve => {
+                reserve.set(options.reserve);
+                return Ok(());
+            }
+            auxdata @ PacketAuxdata => {
+                auxdata.set(options.auxdata);
+                return Ok(());
+            }
+            _ => (),
+        });
+
+        // Deal with socket-level options
+        options.socket.get_option(option, self)
+    }
+
+    fn set_option(&self, option: &dyn SocketOption) -> Result<()> {
+        sock_option_ref!(match option {
+            attach_filter @ AttachFilter => {
+                self.options.read().socket.check_filter_unlocked()?;
+                let filter = attach_filter.get().unwrap().clone();
+                self.receiver.set_filter(Some(filter));
+                return Ok(());
+            }
+            attach_bpf @ AttachBpf => {
+                self.options.read().socket.check_filter_unlocked()?;
+                let filter = SocketFilter::new_ebpf(attach_bpf.get().unwrap().clone())?;
+                self.receiver.set_filter(Some(filter));
+                return Ok(());
+            }
+            _detach_filter @ DetachFilter => {
+                self.options.read().socket.check_filter_unlocked()?;
+                if self.receiver.set_filter(None).is_none() {
+                    return_errno_with_message!(Errno::ENOENT, "no filter is attached");
+                }
+                return Ok(());
+            }
+            rx_ring @ PacketRxRing => {
+                return self.set_rx_ring(rx_ring.get().unwrap());
+            }
+            version @ PacketVersion => {
+                let version = *version.get().unwrap();
+                if version > TPACKET_V3 {
+                    return_errno_with_message!(Errno::EINVAL, "the version is invalid");
+                }
+                return self.set_ring_option(|options| options.version = version);
+            }
+            reserve @ PacketReserve => {
+                let reserve = *reserve.get().unwrap();
+                if reserve > i32::MAX as u32 {
+                    return_errno_with_message!(Errno::EINVAL, "the reserved length is invalid");
+                }
+                return self.set_ring_option(|options| options.reserve = reserve);
+            }
+            add_membership @ PacketAddMembership => {
+                let mreq = add_membership.get().unwrap();
+                self.check_membership(mreq)?;
+                // TODO: Support the promiscuous mode and the link-layer multicast groups.
+                let mut options = self.options.write();
+                if !options.memberships.contains(mreq) {
+                    options.memberships.push(*mreq);
+                }
+                return Ok(());
+            }
+            drop_membership @ PacketDropMembership => {
+                let mreq = drop_membership.get().unwrap();
+                let mut options = self.options.write();
+                let Some(pos) = options.memberships.iter().position(|m| m == mreq) else {
+                    return_errno_with_message!(
+                        Errno::EADDRNOTAVAIL,
+                        "the membership does not exist"
+                    );
+                };
+                options.memberships.remove(pos);
+                return Ok(());
+            }
+            auxdata @ PacketAuxdata => {
+                self.options.write().auxdata = *auxdata.get().unwrap();
+                return Ok(());
+            }
+            _ => (),
+        });
+
+        let mut options = self.options.write();
+
+        // Deal with socket-level options
+        options.socket.set_option(option, self)?;
+        self.receiver.set_recv_buf(options.socket.recv_buf());
+
+        Ok(())
+    }
+
+    fn mappable(&self) -> Result<Mappable> {
+        self.receiver
+            .with_ring(|ring| Mappable::Vmo(ring.vmo().clone()))
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "the receive ring does not exist"))
+    }
+
+    fn pseudo_path(&self) -> &Path {
+        &self.pseudo_path
+    }
+}
+
+impl GetSocketLevelOption for PacketSocket {
+    fn is_listening(&self) -> bool {
+        false
+    }
+}
+
+impl SetSocketLevelOption for PacketSocket {}
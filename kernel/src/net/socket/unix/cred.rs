@@ -2,10 +2,14 @@
 
 use aster_rights::{Dup, Read, ReadDupOp, ReadOp, TRights};
 use aster_rights_proc::require;
+use ostd::task::Task;
 
 use crate::{
     prelude::*,
-    process::{Credentials, Gid, Pid, Uid, posix_thread::AsPosixThread},
+    process::{
+        Credentials, Gid, Pid, Uid, UserNamespace, credentials::capabilities::CapSet,
+        posix_thread::AsPosixThread, process_table,
+    },
 };
 
 pub(super) struct SocketCred<R = ReadOp> {
@@ -36,28 +40,24 @@ impl SocketCred<ReadDupOp> {
 
 impl<R: TRights> SocketCred<R> {
     /// Converts to a [`CUserCred`] with the PID and the _effective_ UID/GID.
+    ///
+    /// The UID/GID are translated to the user namespace of the current thread.
     #[require(R > Read)]
     pub(super) fn to_effective_c_cred(&self) -> CUserCred {
-        CUserCred {
-            pid: self.pid,
-            uid: self.cred.euid(),
-            gid: self.cred.egid(),
-        }
-    }
-
-    /// Converts to a [`CUserCred`] with the PID and the _real_ UID/GID.
-    #[require(R > Read)]
-    pub(super) fn to_real_c_cred(&self) -> CUserCred {
-        CUserCred {
-            pid: self.pid,
-            uid: self.cred.ruid(),
-            gid: self.cred.rgid(),
-        }
+        CUserCred::new_in_current_ns(self.pid, self.cred.euid(), self.cred.egid())
     }
 
+    /// Returns the supplementary groups.
+    ///
+    /// The GIDs are translated to the user namespace of the current thread.
     #[require(R > Read)]
     pub(super) fn groups(&self) -> Arc<[Gid]> {
-        self.cred.groups().iter().cloned().collect()
+        let user_ns = current_user_ns();
+        self.cred
+            .groups()
+            .iter()
+            .map(|gid| user_ns.map_gid_up(*gid).unwrap_or(Gid::OVERFLOW))
+            .collect()
     }
 
     #[require(R > R1)]
@@ -78,6 +78,78 @@ impl<R: TRights> SocketCred<R> {
     }
 }
 
+/// The credentials carried by a UNIX message.
+///
+/// The UID/GID are the ones in the initial user namespace. They are translated to the user
+/// namespace of the receiver when the message is received.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct MessageCred {
+    pid: Pid,
+    uid: Uid,
+    gid: Gid,
+}
+
+impl MessageCred {
+    /// Creates the credentials with the PID and the _real_ UID/GID of the current process.
+    pub(super) fn new_current() -> Self {
+        let cred = current_thread!().as_posix_thread().unwrap().credentials();
+
+        Self {
+            pid: current!().pid(),
+            uid: cred.ruid(),
+            gid: cred.rgid(),
+        }
+    }
+
+    /// Creates the credentials specified in an `SCM_CREDENTIALS` message from the current process.
+    ///
+    /// Like Linux, the current process can specify its own PID and one of its real, effective, and
+    /// saved UIDs/GIDs. Specifying other PIDs, UIDs, and GIDs requires the `CAP_SYS_ADMIN`,
+    /// `CAP_SETUID`, and `CAP_SETGID` capabilities, respectively.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.15/source/net/core/scm.c#L55>.
+    pub(super) fn from_c_cred(c_cred: &CUserCred) -> Result<Self> {
+        let current = Task::current().unwrap();
+        let posix_thread = current.as_posix_thread().unwrap();
+        let user_ns = current.as_thread_local().unwrap().borrow_user_ns();
+        let cred = posix_thread.credentials();
+
+        let (Some(uid), Some(gid)) = (
+            user_ns.map_uid_down(c_cred.uid),
+            user_ns.map_gid_down(c_cred.gid),
+        ) else {
+            return_errno_with_message!(Errno::EINVAL, "the UID or the GID is not mapped");
+        };
+
+        let has_cap = |cap_set| user_ns.check_cap(cap_set, posix_thread).is_ok();
+        // TODO: Translate the PID once PID namespaces are supported.
+        let is_pid_allowed =
+            c_cred.pid == posix_thread.process().pid() || has_cap(CapSet::SYS_ADMIN);
+        let is_uid_allowed =
+            [cred.ruid(), cred.euid(), cred.suid()].contains(&uid) || has_cap(CapSet::SETUID);
+        let is_gid_allowed =
+            [cred.rgid(), cred.egid(), cred.sgid()].contains(&gid) || has_cap(CapSet::SETGID);
+        if !is_pid_allowed || !is_uid_allowed || !is_gid_allowed {
+            return_errno_with_message!(Errno::EPERM, "setting others' credentials is not allowed");
+        }
+
+        if process_table::get_process(c_cred.pid).is_none() {
+            return_errno_with_message!(Errno::ESRCH, "the process does not exist");
+        }
+
+        Ok(Self {
+            pid: c_cred.pid,
+            uid,
+            gid,
+        })
+    }
+
+    /// Converts to a [`CUserCred`] in the user namespace of the current thread.
+    pub(super) fn to_c_cred(&self) -> CUserCred {
+        CUserCred::new_in_current_ns(self.pid, self.uid, self.gid)
+    }
+}
+
 /// `struct ucred` in Linux.
 ///
 /// Reference: <https://elixir.bootlin.com/linux/v6.15/source/include/linux/socket.h#L183>.
@@ -98,6 +170,22 @@ impl CUserCred {
         }
     }
 
+    /// Creates a [`CUserCred`] with the UID/GID translated to the user namespace of the current
+    /// thread.
+    ///
+    /// The UID/GID that are not mapped in the user namespace are translated to the overflow
+    /// UID/GID.
+    fn new_in_current_ns(pid: Pid, uid: Uid, gid: Gid) -> Self {
+        let user_ns = current_user_ns();
+
+        // TODO: Translate the PID once PID namespaces are supported.
+        Self {
+            pid,
+            uid: user_ns.map_uid_up(uid).unwrap_or(Uid::OVERFLOW),
+            gid: user_ns.map_gid_up(gid).unwrap_or(Gid::OVERFLOW),
+        }
+    }
+
     pub(in crate::net) const fn new_overflow() -> Self {
         Self {
             pid: 0,
@@ -106,3 +194,8 @@ impl CUserCred {
         }
     }
 }
+
+fn current_user_ns() -> Arc<UserNamespace> {
+    let current = Task::current().unwrap();
+    current.as_thread_local().unwrap().borrow_user_ns().clone()
+}
@@ -2,10 +2,9 @@
 
 use core::fmt;
 
-use aster_rights::ReadOp;
 use ostd::task::Task;
 
-use super::{CUserCred, UnixStreamSocket, cred::SocketCred};
+use super::{CUserCred, UnixStreamSocket, cred::MessageCred};
 use crate::{
     fs::file::{
         FileLike,
@@ -205,7 +204,7 @@ enum CControlType {
 #[derive(Default)]
 pub(super) struct AuxiliaryData {
     files: Vec<Arc<dyn FileLike>>,
-    cred: Option<SocketCred>,
+    cred: Option<MessageCred>,
 }
 
 impl AuxiliaryData {
@@ -215,8 +214,10 @@ impl AuxiliaryData {
         let mut cred = None;
 
         for ctrl_msg in ctrl_msgs.into_iter() {
-            let ControlMessage::Unix(unix_ctrl_msg) = ctrl_msg;
             // TODO: What should we do if there are control messages of other protocols?
+            let ControlMessage::Unix(unix_ctrl_msg) = ctrl_msg else {
+                continue;
+            };
 
             match unix_ctrl_msg.0 {
                 Message::Files(FileMessage {
@@ -231,15 +232,7 @@ impl AuxiliaryData {
                     files.append(&mut msg_files);
                 }
                 Message::Cred(CredMessage { cred: msg_cred }) => {
-                    let cur_cred = SocketCred::<ReadOp>::new_current();
-                    if cur_cred.to_real_c_cred() != msg_cred {
-                        // FIXME: Allow this if we're root or have the CAP_SYS_ADMIN capability.
-                        return_errno_with_message!(
-                            Errno::EPERM,
-                            "setting others' credentials is not allowed"
-                        );
-                    }
-                    cred = Some(cur_cred);
+                    cred = Some(MessageCred::from_c_cred(&msg_cred)?);
                 }
             }
         }
@@ -268,7 +261,7 @@ impl AuxiliaryData {
     /// Fills the current credentials if there are no credentials.
     pub(super) fn fill_cred(&mut self) {
         if self.cred.is_none() {
-            self.cred = Some(SocketCred::<ReadOp>::new_current());
+            self.cred = Some(MessageCred::new_current());
         }
     }
 
@@ -282,7 +275,7 @@ impl AuxiliaryData {
             let unix_ctrl_msg = UnixControlMessage(Message::Cred(CredMessage {
                 cred: cred
                     .as_ref()
-                    .map(SocketCred::to_real_c_cred)
+                    .map(MessageCred::to_c_cred)
                     .unwrap_or_else(CUserCred::new_overflow),
             }));
             ctrl_msgs.push(ControlMessage::Unix(unix_ctrl_msg));
@@ -312,10 +305,7 @@ impl AuxiliaryData {
             return false;
         }
 
-        if is_pass_cred
-            && self.cred.as_ref().map(SocketCred::to_real_c_cred)
-                != other.cred.as_ref().map(SocketCred::to_real_c_cred)
-        {
+        if is_pass_cred && self.cred != other.cred {
             return false;
         }
 
@@ -507,7 +507,7 @@ impl Socket for UnixStreamSocket {
         }
 
         let (received_bytes, control_messages) =
-            self.block_on(IoEvents::IN, || self.try_recv(writer, flags))?;
+            self.block_on_with_flags(IoEvents::IN, flags, || self.try_recv(writer, flags))?;
 
         let message_header = MessageHeader::new(None, control_messages);
 
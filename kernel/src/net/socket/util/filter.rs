@@ -0,0 +1,411 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Socket filters written in classic BPF or eBPF.
+//!
+//! Reference: <https://www.kernel.org/doc/html/v6.0/networking/filter.html>.
+
+use aster_bigtcp::wire::ETHERNET_HEADER_LEN;
+
+use crate::{
+    bpf::{BpfProg, BpfProgType, SkBuff},
+    prelude::*,
+};
+
+/// A socket filter attached by `SO_ATTACH_FILTER` or `SO_ATTACH_BPF`.
+///
+/// A socket filter is a classic BPF program or an eBPF program. For each packet, the program
+/// returns the number of bytes to keep, where zero means that the packet should be dropped.
+#[derive(Debug, Clone)]
+pub struct SocketFilter {
+    prog: FilterProg,
+}
+
+#[derive(Debug, Clone)]
+enum FilterProg {
+    Classic(Arc<[CSockFilter]>),
+    Ebpf(Arc<BpfProg>),
+}
+
+/// A classic BPF instruction.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/filter.h#L24>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub struct CSockFilter {
+    code: u16,
+    jt: u8,
+    jf: u8,
+    k: u32,
+}
+
+/// The metadata of a packet that the filter can load with ancillary loads.
+#[derive(Debug, Clone, Copy)]
+pub struct FilterMetadata {
+    /// The protocol in host byte order (e.g., `ETH_P_IP`).
+    pub protocol: u16,
+    /// The packet type (e.g., `PACKET_HOST`).
+    pub pkttype: u8,
+    /// The index of the iface.
+    pub ifindex: u32,
+    /// The hardware type of the iface (e.g., `ARPHRD_ETHER`).
+    pub hatype: u16,
+}
+
+impl SocketFilter {
+    /// Creates a socket filter from the instructions.
+    ///
+    /// Like Linux, this method fails with `EINVAL` if the program is empty or too long, contains
+    /// unknown instructions or out-of-bound jumps, or does not end with a return instruction.
+    pub fn new(insns: Vec<CSockFilter>) -> Result<Self> {
+        if insns.is_empty() || insns.len() > BPF_MAXINSNS {
+            return_errno_with_message!(Errno::EINVAL, "the filter length is invalid");
+        }
+
+        for (pc, insn) in insns.iter().enumerate() {
+            check_insn(insn, pc, insns.len())?;
+        }
+
+        let last_code = insns.last().unwrap().code;
+        if last_code != BPF_RET | BPF_K && last_code != BPF_RET | BPF_A {
+            return_errno_with_message!(Errno::EINVAL, "the filter does not end with a return");
+        }
+
+        Ok(Self {
+            prog: FilterProg::Classic(insns.into()),
+        })
+    }
+
+    /// Creates a socket filter from an eBPF program.
+    ///
+    /// This method fails with `EINVAL` if the program is not a socket filter program.
+    pub fn new_ebpf(prog: Arc<BpfProg>) -> Result<Self> {
+        if prog.prog_type() != BpfProgType::SocketFilter {
+            return_errno_with_message!(Errno::EINVAL, "the program is not a socket filter");
+        }
+
+        Ok(Self {
+            prog: FilterProg::Ebpf(prog),
+        })
+    }
+
+    /// Runs the filter on a frame and returns the number of bytes to keep.
+    ///
+    /// The frame starts with the link-layer header. The data seen by the socket starts at
+    /// `data_offset`, which is where positive offsets in the filter are relative to.
+    pub fn run(&self, frame: &[u8], data_offset: usize, metadata: &FilterMetadata) -> u32 {
+        self.run_at(frame, ETHERNET_HEADER_LEN, data_offset, metadata)
+    }
+
+    /// Runs the filter on a message that has no link-layer header (e.g., a netlink message) and
+    /// returns the number of bytes to keep.
+    pub fn run_on_message(&self, message: &[u8], metadata: &FilterMetadata) -> u32 {
+        self.run_at(message, 0, 0, metadata)
+    }
+
+    fn run_at(
+        &self,
+        frame: &[u8],
+        network_offset: usize,
+        data_offset: usize,
+        metadata: &FilterMetadata,
+    ) -> u32 {
+        match &self.prog {
+            FilterProg::Classic(insns) => {
+                Self::run_classic(insns, frame, network_offset, data_offset, metadata)
+            }
+            FilterProg::Ebpf(prog) => {
+                let packet = SkBuff {
+                    data: frame.get(data_offset..).unwrap_or(&[]),
+                    protocol: metadata.protocol,
+                    pkt_type: metadata.pkttype,
+                    ifindex: metadata.ifindex,
+                };
+                prog.run_socket_filter(&packet)
+            }
+        }
+    }
+
+    fn run_classic(
+        insns: &[CSockFilter],
+        frame: &[u8],
+        network_offset: usize,
+        data_offset: usize,
+        metadata: &FilterMetadata,
+    ) -> u32 {
+        let data = frame.get(data_offset..).unwrap_or(&[]);
+        let mut a = 0u32;
+        let mut x = 0u32;
+        let mut mem = [0u32; BPF_MEMWORDS];
+        let mut pc = 0;
+
+        loop {
+            let insn = &insns[pc];
+            pc += 1;
+
+            match insn.code {
+                // Loads into the accumulator
+                code if code == BPF_LD | BPF_IMM => a = insn.k,
+                code if code == BPF_LD | BPF_MEM => a = mem[insn.k as usize],
+                code if code == BPF_LD | BPF_W | BPF_LEN => a = data.len() as u32,
+                code if code & 0x07 == BPF_LD => {
+                    let offset = if code & 0xe0 == BPF_IND {
+                        x.wrapping_add(insn.k)
+                    } else {
+                        insn.k
+                    };
+                    let size = match code & 0x18 {
+                        BPF_W => 4,
+                        BPF_H => 2,
+                        _ => 1,
+                    };
+                    if code & 0xe0 == BPF_ABS
+                        && let Some(value) = load_ancillary(offset, metadata)
+                    {
+                        a = value;
+                        continue;
+                    }
+                    let Some(value) =
+                        load_packet(frame, network_offset, data, offset as i32, size)
+                    else {
+                        return 0;
+                    };
+                    a = value;
+                }
+
+                // Loads into the index register
+                code if code == BPF_LDX | BPF_IMM => x = insn.k,
+                code if code == BPF_LDX | BPF_MEM => x = mem[insn.k as usize],
+                code if code == BPF_LDX | BPF_W | BPF_LEN => x = data.len() as u32,
+                code if code == BPF_LDX | BPF_B | BPF_MSH => {
+                    let Some(value) =
+                        load_packet(frame, network_offset, data, insn.k as i32, 1)
+                    else {
+                        return 0;
+                    };
+                    x = (value & 0xf) << 2;
+                }
+
+                // Stores
+                code if code == BPF_ST => mem[insn.k as usize] = a,
+                code if code == BPF_STX => mem[insn.k as usize] = x,
+
+                // Arithmetic operations
+                code if code == BPF_ALU | BPF_NEG => a = a.wrapping_neg(),
+                code if code & 0x07 == BPF_ALU => {
+                    let src = if code & 0x08 == BPF_X { x } else { insn.k };
+                    a = match code & 0xf0 {
+                        BPF_ADD => a.wrapping_add(src),
+                        BPF_SUB => a.wrapping_sub(src),
+                        BPF_MUL => a.wrapping_mul(src),
+                        BPF_DIV | BPF_MOD if src == 0 => return 0,
+                        BPF_DIV => a / src,
+                        BPF_MOD => a % src,
+                        BPF_OR => a | src,
+                        BPF_AND => a & src,
+                        BPF_XOR => a ^ src,
+                        BPF_LSH => a.checked_shl(src).unwrap_or(0),
+                        BPF_RSH => a.checked_shr(src).unwrap_or(0),
+                        _ => unreachable!(),
+                    };
+                }
+
+                // Jumps
+                code if code == BPF_JMP | BPF_JA => pc += insn.k as usize,
+                code if code & 0x07 == BPF_JMP => {
+                    let src = if code & 0x08 == BPF_X { x } else { insn.k };
+                    let is_true = match code & 0xf0 {
+                        BPF_JEQ => a == src,
+                        BPF_JGT => a > src,
+                        BPF_JGE => a >= src,
+                        BPF_JSET => a & src != 0,
+                        _ => unreachable!(),
+                    };
+                    let offset = if is_true { insn.jt } else { insn.jf };
+                    pc += offset as usize;
+                }
+
+                // Returns
+                code if code == BPF_RET | BPF_K => return insn.k,
+                code if code == BPF_RET | BPF_A => return a,
+
+                // Miscellaneous operations
+                code if code == BPF_MISC | BPF_TAX => x = a,
+                code if code == BPF_MISC | BPF_TXA => a = x,
+
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+/// Checks whether an instruction is valid at the program counter.
+fn check_insn(insn: &CSockFilter, pc: usize, len: usize) -> Result<()> {
+    let code = insn.code;
+
+    let is_valid = match code & 0x07 {
+        BPF_LD => matches!(
+            code,
+            0x00 | 0x20 | 0x28 | 0x30 | 0x40 | 0x48 | 0x50 | 0x60 | 0x80
+        ),
+        BPF_LDX => matches!(code, 0x01 | 0x61 | 0x81 | 0xb1),
+        BPF_ST | BPF_STX => code & !0x07 == 0,
+        BPF_ALU => match code & 0xf0 {
+            BPF_NEG => code == BPF_ALU | BPF_NEG,
+            BPF_ADD | BPF_SUB | BPF_MUL | BPF_DIV | BPF_MOD | BPF_OR | BPF_AND | BPF_XOR
+            | BPF_LSH | BPF_RSH => code & 0xff07 == BPF_ALU,
+            _ => false,
+        },
+        BPF_JMP => match code & 0xf0 {
+            BPF_JA => code == BPF_JMP | BPF_JA,
+            BPF_JEQ | BPF_JGT | BPF_JGE | BPF_JSET => code & 0xff07 == BPF_JMP,
+            _ => false,
+        },
+        BPF_RET => code == BPF_RET | BPF_K || code == BPF_RET | BPF_A,
+        _ => code == BPF_MISC | BPF_TAX || code == BPF_MISC | BPF_TXA,
+    };
+    if !is_valid {
+        return_errno_with_message!(Errno::EINVAL, "the filter contains invalid instructions");
+    }
+
+    let uses_mem = code == BPF_LD | BPF_MEM
+        || code == BPF_LDX | BPF_MEM
+        || code == BPF_ST
+        || code == BPF_STX;
+    if uses_mem && insn.k as usize >= BPF_MEMWORDS {
+        return_errno_with_message!(Errno::EINVAL, "the memory index is out of bounds");
+    }
+
+    if code & 0x07 == BPF_ALU && code & 0x08 == BPF_K {
+        match code & 0xf0 {
+            BPF_DIV | BPF_MOD if insn.k == 0 => {
+                return_errno_with_message!(Errno::EINVAL, "the filter divides by zero");
+            }
+            BPF_LSH | BPF_RSH if insn.k >= 32 => {
+                return_errno_with_message!(Errno::EINVAL, "the shift amount is too large");
+            }
+            _ => (),
+        }
+    }
+
+    if code & 0x07 == BPF_JMP {
+        let remaining = len - pc - 1;
+        let is_in_bounds = if code == BPF_JMP | BPF_JA {
+            (insn.k as usize) < remaining
+        } else {
+            (insn.jt as usize) < remaining && (insn.jf as usize) < remaining
+        };
+        if !is_in_bounds {
+            return_errno_with_message!(Errno::EINVAL, "the jump target is out of bounds");
+        }
+    }
+
+    Ok(())
+}
+
+/// Loads the packet metadata if the offset refers to an ancillary field.
+fn load_ancillary(offset: u32, metadata: &FilterMetadata) -> Option<u32> {
+    let offset = offset as i32;
+    if !(SKF_AD_OFF..0).contains(&offset) {
+        return None;
+    }
+
+    let value = match offset - SKF_AD_OFF {
+        SKF_AD_PROTOCOL => metadata.protocol as u32,
+        SKF_AD_PKTTYPE => metadata.pkttype as u32,
+        SKF_AD_IFINDEX => metadata.ifindex,
+        SKF_AD_HATYPE => metadata.hatype as u32,
+        // TODO: Support other ancillary fields.
+        _ => 0,
+    };
+    Some(value)
+}
+
+/// Loads a big-endian value from the packet.
+///
+/// Negative offsets are relative to the network header (`SKF_NET_OFF`), which starts at
+/// `network_offset`, or the link-layer header (`SKF_LL_OFF`) in the frame, while positive offsets
+/// are relative to the socket data.
+fn load_packet(
+    frame: &[u8],
+    network_offset: usize,
+    data: &[u8],
+    offset: i32,
+    size: usize,
+) -> Option<u32> {
+    let bytes = if offset >= 0 {
+        data.get(offset as usize..)?
+    } else if offset >= SKF_NET_OFF {
+        frame.get(network_offset + (offset - SKF_NET_OFF) as usize..)?
+    } else if offset >= SKF_LL_OFF {
+        frame.get((offset - SKF_LL_OFF) as usize..)?
+    } else {
+        return None;
+    };
+
+    let bytes = bytes.get(..size)?;
+    Some(bytes.iter().fold(0, |value, byte| (value << 8) | *byte as u32))
+}
+
+const BPF_MAXINSNS: usize = 4096;
+const BPF_MEMWORDS: usize = 16;
+
+// Instruction classes
+const BPF_LD: u16 = 0x00;
+const BPF_LDX: u16 = 0x01;
+const BPF_ST: u16 = 0x02;
+const BPF_STX: u16 = 0x03;
+const BPF_ALU: u16 = 0x04;
+const BPF_JMP: u16 = 0x05;
+const BPF_RET: u16 = 0x06;
+const BPF_MISC: u16 = 0x07;
+
+// Load sizes
+const BPF_W: u16 = 0x00;
+const BPF_H: u16 = 0x08;
+const BPF_B: u16 = 0x10;
+
+// Load modes
+const BPF_IMM: u16 = 0x00;
+const BPF_ABS: u16 = 0x20;
+const BPF_IND: u16 = 0x40;
+const BPF_MEM: u16 = 0x60;
+const BPF_LEN: u16 = 0x80;
+const BPF_MSH: u16 = 0xa0;
+
+// Arithmetic operations
+const BPF_ADD: u16 = 0x00;
+const BPF_SUB: u16 = 0x10;
+const BPF_MUL: u16 = 0x20;
+const BPF_DIV: u16 = 0x30;
+const BPF_OR: u16 = 0x40;
+const BPF_AND: u16 = 0x50;
+const BPF_LSH: u16 = 0x60;
+const BPF_RSH: u16 = 0x70;
+const BPF_NEG: u16 = 0x80;
+const BPF_MOD: u16 = 0x90;
+const BPF_XOR: u16 = 0xa0;
+
+// Jump operations
+const BPF_JA: u16 = 0x00;
+const BPF_JEQ: u16 = 0x10;
+const BPF_JGT: u16 = 0x20;
+const BPF_JGE: u16 = 0x30;
+const BPF_JSET: u16 = 0x40;
+
+// Operand sources
+const BPF_K: u16 = 0x00;
+const BPF_X: u16 = 0x08;
+const BPF_A: u16 = 0x10;
+
+// Miscellaneous operations
+const BPF_TAX: u16 = 0x00;
+const BPF_TXA: u16 = 0x80;
+
+// Special offsets
+const SKF_AD_OFF: i32 = -0x1000;
+const SKF_AD_PROTOCOL: i32 = 0;
+const SKF_AD_PKTTYPE: i32 = 4;
+const SKF_AD_IFINDEX: i32 = 8;
+const SKF_AD_HATYPE: i32 = 28;
+const SKF_NET_OFF: i32 = -0x100000;
+const SKF_LL_OFF: i32 = -0x200000;
@@ -2,8 +2,16 @@
 
 use align_ext::AlignExt;
 
-use super::SocketAddr;
-use crate::{net::socket::unix::UnixControlMessage, prelude::*, util::net::CSocketOptionLevel};
+use super::{SocketAddr, TimestampControlMessage};
+use crate::{
+    net::socket::{
+        alg::AlgControlMessage,
+        ip::{IpControlMessage, TlsControlMessage},
+        unix::UnixControlMessage,
+    },
+    prelude::*,
+    util::net::CSocketOptionLevel,
+};
 
 /// Message header used for sendmsg/recvmsg.
 #[derive(Debug)]
@@ -36,6 +44,10 @@ impl MessageHeader {
 #[derive(Debug)]
 pub enum ControlMessage {
     Unix(UnixControlMessage),
+    Ip(IpControlMessage),
+    Timestamp(TimestampControlMessage),
+    Tls(TlsControlMessage),
+    Alg(AlgControlMessage),
 }
 
 impl ControlMessage {
@@ -90,6 +102,14 @@ impl ControlMessage {
                 let msg = UnixControlMessage::read_from(header, reader)?;
                 Ok(msg.map(Self::Unix))
             }
+            CSocketOptionLevel::SOL_TLS => {
+                let msg = TlsControlMessage::read_from(header, reader)?;
+                Ok(msg.map(Self::Tls))
+            }
+            CSocketOptionLevel::SOL_ALG => {
+                let msg = AlgControlMessage::read_from(header, reader)?;
+                Ok(msg.map(Self::Alg))
+            }
             _ => {
                 warn!("unsupported control message level in {:?}", header);
                 reader.skip(header.payload_len());
@@ -127,6 +147,10 @@ impl ControlMessage {
     fn write_to(&self, writer: &mut VmWriter) -> Result<CControlHeader> {
         match self {
             Self::Unix(msg) => msg.write_to(writer),
+            Self::Ip(msg) => msg.write_to(writer),
+            Self::Timestamp(msg) => msg.write_to(writer),
+            Self::Tls(msg) => msg.write_to(writer),
+            Self::Alg(msg) => msg.write_to(writer),
         }
     }
 }
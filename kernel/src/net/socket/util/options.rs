@@ -2,20 +2,30 @@
 
 use core::ops::RangeInclusive;
 
-use aster_bigtcp::socket::{
-    NeedIfacePoll, TCP_RECV_BUF_LEN, TCP_SEND_BUF_LEN, UDP_RECV_PAYLOAD_LEN, UDP_SEND_PAYLOAD_LEN,
+use aster_bigtcp::{
+    iface::PortReuse,
+    socket::{
+        NeedIfacePoll, PACKET_SEND_BUF_LEN, RAW_RECV_BUF_LEN, RAW_SEND_BUF_LEN, TCP_RECV_BUF_LEN,
+        TCP_SEND_BUF_LEN, UDP_RECV_PAYLOAD_LEN, UDP_SEND_PAYLOAD_LEN,
+    },
 };
 
-use super::LingerOption;
+use super::{LingerOption, TimestampingFlags};
 use crate::{
-    net::socket::{
-        netlink::NETLINK_DEFAULT_BUF_SIZE,
-        options::{
-            AcceptConn, Broadcast, KeepAlive, Linger, PassCred, PeerCred, PeerGroups, Priority,
-            RecvBuf, RecvBufForce, ReuseAddr, ReusePort, SendBuf, SendBufForce, SocketOption,
-            macros::{sock_option_mut, sock_option_ref},
+    net::{
+        iface::iter_all_ifaces,
+        socket::{
+            netlink::NETLINK_DEFAULT_BUF_SIZE,
+            options::{
+                AcceptConn, BindToDevice, Broadcast, KeepAlive, Linger, LockFilter, PassCred,
+                PeerCred, PeerGroups, Priority, RecvBuf, RecvBufForce, ReuseAddr, ReusePort,
+                SendBuf, SendBufForce, SocketOption, Timestamp, TimestampNs, Timestamping,
+                Zerocopy,
+                macros::{sock_option_mut, sock_option_ref},
+            },
+            packet::PACKET_RECV_BUF_LEN,
+            unix::{CUserCred, UNIX_DATAGRAM_DEFAULT_BUF_SIZE, UNIX_STREAM_DEFAULT_BUF_SIZE},
         },
-        unix::{CUserCred, UNIX_DATAGRAM_DEFAULT_BUF_SIZE, UNIX_STREAM_DEFAULT_BUF_SIZE},
     },
     prelude::*,
     process::{credentials::capabilities::CapSet, posix_thread::AsPosixThread},
@@ -34,6 +44,18 @@ pub struct SocketOptionSet {
     linger: LingerOption,
     reuse_port: bool,
     pass_cred: bool,
+    zerocopy: bool,
+    /// Whether `SCM_TIMESTAMP` or `SCM_TIMESTAMPNS` is reported for received packets.
+    timestamp: bool,
+    /// Whether `SCM_TIMESTAMPNS` is reported instead of `SCM_TIMESTAMP`.
+    timestamp_ns: bool,
+    timestamping: TimestampingFlags,
+    /// The index of the iface that the socket is bound to (`SO_BINDTODEVICE`), or zero if the
+    /// socket is not bound to any iface.
+    bound_ifindex: u32,
+    /// Whether the socket filter is locked (`SO_LOCK_FILTER`), in which case it can no longer be
+    /// attached or detached.
+    lock_filter: bool,
 }
 
 impl Default for SocketOptionSet {
@@ -48,6 +70,12 @@ impl Default for SocketOptionSet {
             linger: LingerOption::default(),
             reuse_port: false,
             pass_cred: false,
+            zerocopy: false,
+            timestamp: false,
+            timestamp_ns: false,
+            timestamping: TimestampingFlags::empty(),
+            bound_ifindex: 0,
+            lock_filter: false,
         }
     }
 }
@@ -71,6 +99,24 @@ impl SocketOptionSet {
         }
     }
 
+    /// Returns the default socket level options for raw socket.
+    pub(in crate::net) fn new_raw() -> Self {
+        Self {
+            send_buf: RAW_SEND_BUF_LEN as u32,
+            recv_buf: RAW_RECV_BUF_LEN as u32,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the default socket level options for packet socket.
+    pub(in crate::net) fn new_packet() -> Self {
+        Self {
+            send_buf: PACKET_SEND_BUF_LEN as u32,
+            recv_buf: PACKET_RECV_BUF_LEN as u32,
+            ..Default::default()
+        }
+    }
+
     /// Returns the default socket level options for unix stream socket.
     pub(in crate::net) fn new_unix_stream() -> Self {
         Self {
@@ -98,6 +144,23 @@ impl SocketOptionSet {
         }
     }
 
+    /// Returns how the port bound by the socket can be shared with other sockets.
+    pub(in crate::net) fn port_reuse(&self) -> PortReuse {
+        let mut reuse = PortReuse::empty();
+        reuse.set(PortReuse::ADDR, self.reuse_addr);
+        reuse.set(PortReuse::PORT, self.reuse_port);
+        reuse
+    }
+
+    /// Checks whether the socket filter can be attached or detached.
+    pub(in crate::net) fn check_filter_unlocked(&self) -> Result<()> {
+        if self.lock_filter {
+            return_errno_with_message!(Errno::EPERM, "the socket filter is locked");
+        }
+
+        Ok(())
+    }
+
     /// Gets socket-level options.
     ///
     /// Note that the socket error has to be handled separately. This method does not handle it
@@ -167,6 +230,30 @@ impl SocketOptionSet {
             _socket_peer_groups @ PeerGroups => {
                 return_errno_with_message!(Errno::ENODATA, "the socket does not have peer groups");
             }
+            socket_zerocopy @ Zerocopy => {
+                let zerocopy = self.zerocopy();
+                socket_zerocopy.set(zerocopy);
+            }
+            socket_timestamp @ Timestamp => {
+                let timestamp = self.timestamp() && !self.timestamp_ns();
+                socket_timestamp.set(timestamp);
+            }
+            socket_timestamp_ns @ TimestampNs => {
+                let timestamp_ns = self.timestamp_ns();
+                socket_timestamp_ns.set(timestamp_ns);
+            }
+            socket_timestamping @ Timestamping => {
+                let timestamping = self.timestamping();
+                socket_timestamping.set(timestamping);
+            }
+            socket_bind_to_device @ BindToDevice => {
+                let name = iface_name_from_index(self.bound_ifindex())?;
+                socket_bind_to_device.set(name);
+            }
+            socket_lock_filter @ LockFilter => {
+                let lock_filter = self.lock_filter();
+                socket_lock_filter.set(lock_filter);
+            }
             _ => return_errno_with_message!(
                 Errno::ENOPROTOOPT,
                 "the socket option to get is unknown"
@@ -224,6 +311,7 @@ impl SocketOptionSet {
             socket_reuse_port @ ReusePort => {
                 let reuse_port = socket_reuse_port.get().unwrap();
                 self.set_reuse_port(*reuse_port);
+                socket.set_reuse_port(*reuse_port);
             }
             socket_pass_cred @ PassCred => {
                 // This option only affects UNIX sockets. However, it also works well with other
@@ -250,6 +338,43 @@ impl SocketOptionSet {
                     self.set_recv_buf(*recv_buf);
                 }
             }
+            socket_zerocopy @ Zerocopy => {
+                let zerocopy = socket_zerocopy.get().unwrap();
+                socket.set_zerocopy(*zerocopy)?;
+                self.set_zerocopy(*zerocopy);
+            }
+            socket_timestamp @ Timestamp => {
+                // Enabling `SO_TIMESTAMP` overrides `SO_TIMESTAMPNS`, and vice versa. Disabling
+                // either of them disables both.
+                let timestamp = socket_timestamp.get().unwrap();
+                self.set_timestamp(*timestamp);
+                self.set_timestamp_ns(false);
+            }
+            socket_timestamp_ns @ TimestampNs => {
+                let timestamp_ns = socket_timestamp_ns.get().unwrap();
+                self.set_timestamp(*timestamp_ns);
+                self.set_timestamp_ns(*timestamp_ns);
+            }
+            socket_timestamping @ Timestamping => {
+                let timestamping = socket_timestamping.get().unwrap();
+                check_timestamping(*timestamping)?;
+                self.set_timestamping(*timestamping);
+            }
+            socket_bind_to_device @ BindToDevice => {
+                // FIXME: The option takes effect only when the socket is bound to a local address,
+                // so it has no effect on the sockets that have already been bound.
+                let name = socket_bind_to_device.get().unwrap();
+                let ifindex = iface_index_from_name(name)?;
+                self.set_bound_ifindex(ifindex);
+            }
+            socket_lock_filter @ LockFilter => {
+                // Like Linux, the lock cannot be released once the filter is locked.
+                let lock_filter = socket_lock_filter.get().unwrap();
+                if !*lock_filter {
+                    self.check_filter_unlocked()?;
+                }
+                self.set_lock_filter(*lock_filter);
+            }
             _ => return_errno_with_message!(
                 Errno::ENOPROTOOPT,
                 "the socket option to be set is unknown"
@@ -284,6 +409,46 @@ fn check_priority(priority: i32) -> Result<()> {
     check_current_privileged()
 }
 
+fn check_timestamping(flags: TimestampingFlags) -> Result<()> {
+    if flags.contains(TimestampingFlags::OPT_ID_TCP) && !flags.contains(TimestampingFlags::OPT_ID) {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "SOF_TIMESTAMPING_OPT_ID_TCP requires SOF_TIMESTAMPING_OPT_ID"
+        );
+    }
+
+    if flags.contains(TimestampingFlags::BIND_PHC) {
+        // There are no PTP hardware clocks to bind to.
+        return_errno_with_message!(Errno::EINVAL, "binding to a PHC is not supported");
+    }
+
+    Ok(())
+}
+
+/// Looks up the index of the iface with the name, or returns zero if the name is empty.
+fn iface_index_from_name(name: &str) -> Result<u32> {
+    if name.is_empty() {
+        return Ok(0);
+    }
+
+    iter_all_ifaces()
+        .find(|iface| iface.name() == name)
+        .map(|iface| iface.index())
+        .ok_or_else(|| Error::with_message(Errno::ENODEV, "the interface does not exist"))
+}
+
+/// Looks up the name of the iface with the index, or returns an empty name if the index is zero.
+fn iface_name_from_index(index: u32) -> Result<String> {
+    if index == 0 {
+        return Ok(String::new());
+    }
+
+    iter_all_ifaces()
+        .find(|iface| iface.index() == index)
+        .map(|iface| iface.name().to_string())
+        .ok_or_else(|| Error::with_message(Errno::ENXIO, "the interface no longer exists"))
+}
+
 pub const MIN_SENDBUF: u32 = 2304;
 pub const MIN_RECVBUF: u32 = 2304;
 
@@ -298,10 +463,21 @@ pub(in crate::net) trait SetSocketLevelOption {
     /// Sets whether the socket address can be reused.
     fn set_reuse_addr(&self, _reuse_addr: bool) {}
 
+    /// Sets whether the socket port can be shared by a load-balanced group of sockets.
+    fn set_reuse_port(&self, _reuse_port: bool) {}
+
     /// Sets whether keepalive messages are enabled.
     fn set_keep_alive(&self, _keep_alive: bool) -> NeedIfacePoll {
         NeedIfacePoll::FALSE
     }
     /// Sets whether receipt of the credentials of the sending process is enabled.
     fn set_pass_cred(&self, _pass_cred: bool) {}
+
+    /// Sets whether zero-copy transmission (`MSG_ZEROCOPY`) is enabled.
+    fn set_zerocopy(&self, _zerocopy: bool) -> Result<()> {
+        return_errno_with_message!(
+            Errno::EOPNOTSUPP,
+            "zero-copy transmission is not supported by the socket"
+        );
+    }
 }
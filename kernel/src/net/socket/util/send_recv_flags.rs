@@ -32,6 +32,8 @@ bitflags! {
         // const MSG_EOF         MSG_FIN
         const MSG_NO_SHARED_FRAGS = 0x80000; /* sendpage() internal : page frags are not shared */
         const MSG_SENDPAGE_DECRYPTED	= 0x100000; /* sendpage() internal : page may carry plain text and require encryption */
+        const MSG_ZEROCOPY	= 0x4000000;	/* Use user data in kernel path */
+        const MSG_FASTOPEN	= 0x20000000; /* Send data in TCP SYN */
     }
 }
 
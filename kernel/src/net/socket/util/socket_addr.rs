@@ -1,9 +1,12 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use aster_bigtcp::wire::{Ipv4Address, PortNum};
+use aster_bigtcp::wire::{Ipv4Address, Ipv6Address, PortNum};
 
 use crate::{
-    net::socket::{netlink::NetlinkSocketAddr, unix::UnixSocketAddr, vsock::addr::VsockSocketAddr},
+    net::socket::{
+        alg::AlgSocketAddr, netlink::NetlinkSocketAddr, packet::PacketSocketAddr,
+        unix::UnixSocketAddr, vsock::addr::VsockSocketAddr,
+    },
     prelude::*,
 };
 
@@ -11,6 +14,9 @@ use crate::{
 pub enum SocketAddr {
     Unix(UnixSocketAddr),
     IPv4(Ipv4Address, PortNum),
+    IPv6(Ipv6Address, PortNum),
     Netlink(NetlinkSocketAddr),
     Vsock(VsockSocketAddr),
+    Packet(PacketSocketAddr),
+    Alg(AlgSocketAddr),
 }
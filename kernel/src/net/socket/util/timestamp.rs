@@ -0,0 +1,134 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use super::{CControlHeader, ControlMessage, options::SocketOptionSet};
+use crate::{
+    prelude::*,
+    time::{timespec_t, timeval_t},
+    util::net::CSocketOptionLevel,
+};
+
+bitflags! {
+    /// Flags used for `SO_TIMESTAMPING`.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/net_tstamp.h#L17>.
+    pub struct TimestampingFlags: u32 {
+        const TX_HARDWARE   = 1 << 0;
+        const TX_SOFTWARE   = 1 << 1;
+        const RX_HARDWARE   = 1 << 2;
+        const RX_SOFTWARE   = 1 << 3;
+        const SOFTWARE      = 1 << 4;
+        const SYS_HARDWARE  = 1 << 5;
+        const RAW_HARDWARE  = 1 << 6;
+        const OPT_ID        = 1 << 7;
+        const TX_SCHED      = 1 << 8;
+        const TX_ACK        = 1 << 9;
+        const OPT_CMSG      = 1 << 10;
+        const OPT_TSONLY    = 1 << 11;
+        const OPT_STATS     = 1 << 12;
+        const OPT_PKTINFO   = 1 << 13;
+        const OPT_TX_SWHW   = 1 << 14;
+        const BIND_PHC      = 1 << 15;
+        const OPT_ID_TCP    = 1 << 16;
+        const OPT_RX_FILTER = 1 << 17;
+    }
+}
+
+/// A control message that carries the timestamp of a packet.
+#[derive(Debug)]
+pub struct TimestampControlMessage {
+    kind: Kind,
+    timestamp: Duration,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    /// `SCM_TIMESTAMP`, which carries a `struct timeval`.
+    Timeval,
+    /// `SCM_TIMESTAMPNS`, which carries a `struct timespec`.
+    Timespec,
+    /// `SCM_TIMESTAMPING`, which carries a `struct scm_timestamping`.
+    Timestamping,
+}
+
+impl TimestampControlMessage {
+    /// Creates the control messages that report the timestamp of a packet.
+    ///
+    /// Which control messages are created depends on the `SO_TIMESTAMP`, `SO_TIMESTAMPNS`, and
+    /// `SO_TIMESTAMPING` options. The timestamp is the duration since the Unix epoch.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.13/source/net/socket.c#L895>.
+    pub(in crate::net) fn new_all(
+        options: &SocketOptionSet,
+        timestamp: Duration,
+        is_err_queue: bool,
+    ) -> Vec<ControlMessage> {
+        let mut msgs = Vec::new();
+
+        if options.timestamp_ns() {
+            msgs.push(Self::new(Kind::Timespec, timestamp));
+        } else if options.timestamp() {
+            msgs.push(Self::new(Kind::Timeval, timestamp));
+        }
+
+        // With `SOF_TIMESTAMPING_OPT_RX_FILTER`, the software timestamps of received packets are
+        // reported only if they are requested by `SOF_TIMESTAMPING_RX_SOFTWARE`.
+        let flags = options.timestamping();
+        if flags.contains(TimestampingFlags::SOFTWARE)
+            && (flags.contains(TimestampingFlags::RX_SOFTWARE)
+                || is_err_queue
+                || !flags.contains(TimestampingFlags::OPT_RX_FILTER))
+        {
+            msgs.push(Self::new(Kind::Timestamping, timestamp));
+        }
+
+        msgs
+    }
+
+    fn new(kind: Kind, timestamp: Duration) -> ControlMessage {
+        ControlMessage::Timestamp(Self { kind, timestamp })
+    }
+
+    pub fn write_to(&self, writer: &mut VmWriter) -> Result<CControlHeader> {
+        let (type_, payload) = match self.kind {
+            Kind::Timeval => {
+                let timeval = timeval_t::from(self.timestamp);
+                (SCM_TIMESTAMP, timeval.as_bytes().to_vec())
+            }
+            Kind::Timespec => {
+                let timespec = timespec_t::from(self.timestamp);
+                (SCM_TIMESTAMPNS, timespec.as_bytes().to_vec())
+            }
+            Kind::Timestamping => {
+                // The first timestamp is the software timestamp. The last one is the hardware
+                // timestamp, which is not supported. The other one is deprecated.
+                let timespecs = [
+                    timespec_t::from(self.timestamp),
+                    timespec_t::default(),
+                    timespec_t::default(),
+                ];
+                (SCM_TIMESTAMPING, timespecs.as_bytes().to_vec())
+            }
+        };
+
+        let payload_len =
+            payload.len().min(CControlHeader::payload_len_from_total(writer.avail())?);
+        if payload_len != payload.len() {
+            warn!("setting MSG_CTRUNC is not supported");
+        }
+
+        let header = CControlHeader::new(CSocketOptionLevel::SOL_SOCKET, type_, payload_len);
+        writer.write_val(&header)?;
+        writer.write_fallible(&mut VmReader::from(&payload[..payload_len]))?;
+
+        Ok(header)
+    }
+}
+
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/asm-generic/socket.h#L146>.
+const SCM_TIMESTAMP: i32 = 29;
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/asm-generic/socket.h#L147>.
+const SCM_TIMESTAMPNS: i32 = 35;
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/asm-generic/socket.h#L148>.
+const SCM_TIMESTAMPING: i32 = 37;
@@ -13,7 +13,7 @@ use super::{
     addr::VsockSocketAddr,
     stream::{
         connected::{Connected, ConnectionID},
-        connecting::Connecting,
+        connecting::{ConnResult, Connecting},
         listen::Listen,
     },
 };
@@ -236,8 +236,9 @@ impl VsockSpace {
                     listen.push_incoming(connected).unwrap();
                 }
                 VsockEventType::ConnectionResponse => {
-                    let connecting_sockets = self.connecting_sockets.disable_irq().lock();
-                    let Some(connecting) = connecting_sockets.get(&event.destination.into()) else {
+                    let mut connecting_sockets = self.connecting_sockets.disable_irq().lock();
+                    let Some(connecting) = connecting_sockets.remove(&event.destination.into())
+                    else {
                         return_errno_with_message!(
                             Errno::EINVAL,
                             "connected event can only be handled by connecting socket"
@@ -249,14 +250,26 @@ impl VsockSpace {
                         connecting.local_addr()
                     );
                     connecting.update_info(&event);
-                    connecting.set_connected();
+
+                    // The connected socket must be inserted before handling the next event, which
+                    // may carry data for the connection.
+                    let connected = Arc::new(Connected::from_connecting(&connecting));
+                    self.insert_connected_socket(connected.id(), connected.clone());
+                    connecting.set_result(ConnResult::Connected(connected));
                 }
                 VsockEventType::Disconnected { .. } => {
-                    let connected_sockets = self.connected_sockets.read();
-                    let Some(connected) = connected_sockets.get(&event.into()) else {
+                    if let Some(connected) = self.connected_sockets.read().get(&event.into()) {
+                        connected.set_peer_requested_shutdown();
+                        continue;
+                    }
+
+                    // The peer refuses the connection request by resetting the connection.
+                    let mut connecting_sockets = self.connecting_sockets.disable_irq().lock();
+                    let Some(connecting) = connecting_sockets.remove(&event.destination.into())
+                    else {
                         return_errno_with_message!(Errno::ENOTCONN, "the socket hasn't connected");
                     };
-                    connected.set_peer_requested_shutdown();
+                    connecting.set_result(ConnResult::Reset);
                 }
                 VsockEventType::Received { .. } => {}
                 VsockEventType::CreditRequest => {
@@ -32,7 +32,7 @@ impl Connected {
         }
     }
 
-    pub fn from_connecting(connecting: Arc<Connecting>) -> Self {
+    pub fn from_connecting(connecting: &Connecting) -> Self {
         Self {
             connection: SpinLock::new(Connection::new_from_info(connecting.info())),
             id: connecting.id(),
@@ -58,16 +58,13 @@ impl Connected {
         connection.info.done_forwarding(bytes_read);
         self.pollee.invalidate();
 
-        match bytes_read {
-            0 => {
-                if !connection.is_peer_requested_shutdown() {
-                    return_errno_with_message!(Errno::EAGAIN, "the receive buffer is empty");
-                } else {
-                    return_errno_with_message!(Errno::ECONNRESET, "the connection is reset");
-                }
-            }
-            bytes_read => Ok(bytes_read),
+        // Like Linux, reading from a connection shut down by the peer returns zero (i.e., the end
+        // of file) once the buffered data has been consumed.
+        if bytes_read == 0 && !connection.is_peer_requested_shutdown() {
+            return_errno_with_message!(Errno::EAGAIN, "the receive buffer is empty");
         }
+
+        Ok(bytes_read)
     }
 
     pub fn send(&self, reader: &mut dyn MultiRead, flags: SendRecvFlags) -> Result<usize> {
@@ -133,7 +130,8 @@ impl Connected {
         self.connection
             .disable_irq()
             .lock()
-            .set_peer_requested_shutdown()
+            .set_peer_requested_shutdown();
+        self.pollee.notify(IoEvents::IN | IoEvents::RDHUP);
     }
 
     pub fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
@@ -144,12 +142,24 @@ impl Connected {
     fn check_io_events(&self) -> IoEvents {
         let connection = self.connection.disable_irq().lock();
 
+        let mut events = IoEvents::empty();
+
         // receive
         if !connection.buffer.is_empty() {
-            IoEvents::IN
-        } else {
-            IoEvents::empty()
+            events |= IoEvents::IN;
+        }
+        if connection.is_peer_requested_shutdown() {
+            events |= IoEvents::IN | IoEvents::RDHUP;
         }
+
+        // send
+        // FIXME: The send buffer of the driver is not checked, so the socket is always writable
+        // until it is shut down.
+        if !connection.is_local_shutdown() {
+            events |= IoEvents::OUT;
+        }
+
+        events
     }
 }
 
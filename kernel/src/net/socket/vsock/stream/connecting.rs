@@ -1,13 +1,12 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use core::sync::atomic::{AtomicBool, Ordering};
-
 use aster_virtio::device::socket::connect::{ConnectionInfo, VsockEvent};
+use spin::Once;
 
-use super::connected::ConnectionID;
+use super::connected::{Connected, ConnectionID};
 use crate::{
     events::IoEvents,
-    net::socket::vsock::{VSOCK_GLOBAL, addr::VsockSocketAddr},
+    net::socket::vsock::addr::VsockSocketAddr,
     prelude::*,
     process::signal::{PollHandle, Pollee},
 };
@@ -15,16 +14,24 @@ use crate::{
 pub struct Connecting {
     id: ConnectionID,
     info: SpinLock<ConnectionInfo>,
-    is_connected: AtomicBool,
+    result: Once<ConnResult>,
     pollee: Pollee,
 }
 
+/// The result of a connection request.
+pub enum ConnResult {
+    /// The peer has accepted the connection request.
+    Connected(Arc<Connected>),
+    /// The peer has reset the connection.
+    Reset,
+}
+
 impl Connecting {
     pub fn new(peer_addr: VsockSocketAddr, local_addr: VsockSocketAddr) -> Self {
         Self {
             info: SpinLock::new(ConnectionInfo::new(peer_addr.into(), local_addr.port)),
             id: ConnectionID::new(local_addr, peer_addr),
-            is_connected: AtomicBool::new(false),
+            result: Once::new(),
             pollee: Pollee::new(),
         }
     }
@@ -49,29 +56,26 @@ impl Connecting {
         self.info.disable_irq().lock().update_for_event(event)
     }
 
+    /// Returns the result of the connection request, or `None` if the peer has not responded yet.
+    pub fn result(&self) -> Option<&ConnResult> {
+        self.result.get()
+    }
+
+    pub fn set_result(&self, result: ConnResult) {
+        self.result.call_once(|| result);
+        self.pollee.notify(IoEvents::OUT);
+    }
+
     pub fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
         self.pollee
             .poll_with(mask, poller, || self.check_io_events())
     }
 
     fn check_io_events(&self) -> IoEvents {
-        if self.is_connected.load(Ordering::Relaxed) {
-            IoEvents::IN
+        if self.result.is_completed() {
+            IoEvents::OUT
         } else {
             IoEvents::empty()
         }
     }
-
-    pub fn set_connected(&self) {
-        self.is_connected.store(true, Ordering::Relaxed);
-        self.pollee.notify(IoEvents::IN);
-    }
-}
-
-impl Drop for Connecting {
-    fn drop(&mut self) {
-        let vsockspace = VSOCK_GLOBAL.get().unwrap();
-        vsockspace.recycle_port(&self.local_addr().port);
-        vsockspace.remove_connecting_socket(&self.local_addr());
-    }
 }
@@ -1,5 +1,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use core::sync::atomic::{AtomicBool, Ordering};
+
 use crate::{
     events::IoEvents,
     net::socket::vsock::{
@@ -12,12 +14,25 @@ use crate::{
 
 pub struct Init {
     bound_addr: Mutex<Option<VsockSocketAddr>>,
+    is_conn_reset: AtomicBool,
 }
 
 impl Init {
     pub fn new() -> Self {
         Self {
             bound_addr: Mutex::new(None),
+            is_conn_reset: AtomicBool::new(false),
+        }
+    }
+
+    /// Creates a socket whose connection request to the peer has been reset.
+    ///
+    /// The socket stays bound to the local address, and the error is reported via
+    /// [`Self::test_and_clear_error`].
+    pub fn new_reset(bound_addr: VsockSocketAddr) -> Self {
+        Self {
+            bound_addr: Mutex::new(Some(bound_addr)),
+            is_conn_reset: AtomicBool::new(true),
         }
     }
 
@@ -55,8 +70,23 @@ impl Init {
         *self.bound_addr.lock()
     }
 
+    pub fn test_and_clear_error(&self) -> Option<Error> {
+        if self.is_conn_reset.swap(false, Ordering::Relaxed) {
+            Some(Error::with_message(
+                Errno::ECONNRESET,
+                "the connection is reset by the peer",
+            ))
+        } else {
+            None
+        }
+    }
+
     pub fn poll(&self, _mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
-        IoEvents::empty()
+        if self.is_conn_reset.load(Ordering::Relaxed) {
+            IoEvents::ERR
+        } else {
+            IoEvents::empty()
+        }
     }
 }
 
@@ -2,18 +2,26 @@
 
 use core::sync::atomic::{AtomicBool, Ordering};
 
-use super::{connected::Connected, connecting::Connecting, init::Init, listen::Listen};
+use ostd::sync::{PreemptDisabled, RwLockReadGuard, RwLockWriteGuard};
+
+use super::{
+    connected::Connected,
+    connecting::{ConnResult, Connecting},
+    init::Init,
+    listen::Listen,
+};
 use crate::{
     events::IoEvents,
     fs::{file::FileLike, pseudofs::SockFs, vfs::path::Path},
     net::socket::{
         Socket,
+        options::{Error as SocketError, SocketOption, macros::sock_option_mut},
         private::SocketPrivate,
         util::{MessageHeader, SendRecvFlags, SockShutdownCmd, SocketAddr},
         vsock::{VSOCK_GLOBAL, addr::VsockSocketAddr},
     },
     prelude::*,
-    process::signal::{PollHandle, Pollable, Poller},
+    process::signal::{PollHandle, Pollable},
     util::{MultiRead, MultiWrite},
 };
 
@@ -25,10 +33,30 @@ pub struct VsockStreamSocket {
 
 pub enum Status {
     Init(Arc<Init>),
+    Connecting(Arc<Connecting>),
     Listen(Arc<Listen>),
     Connected(Arc<Connected>),
 }
 
+impl Status {
+    /// Moves a connecting socket to the connected state or the initial state if the peer has
+    /// responded to the connection request.
+    fn update(&mut self) {
+        let Status::Connecting(connecting) = self else {
+            return;
+        };
+
+        let new_status = match connecting.result() {
+            Some(ConnResult::Connected(connected)) => Status::Connected(connected.clone()),
+            Some(ConnResult::Reset) => {
+                Status::Init(Arc::new(Init::new_reset(connecting.local_addr())))
+            }
+            None => return,
+        };
+        *self = new_status;
+    }
+}
+
 impl VsockStreamSocket {
     pub fn new(nonblocking: bool) -> Result<Self> {
         if VSOCK_GLOBAL.get().is_none() {
@@ -54,10 +82,115 @@ impl VsockStreamSocket {
         }
     }
 
+    /// Ensures that the socket status is up to date and obtains a read lock on it.
+    ///
+    /// The connection request is handled in the interrupt handler, where the socket status is not
+    /// accessible. So the status transition is delayed until the user operates on the socket.
+    fn read_updated_status(&self) -> RwLockReadGuard<'_, Status, PreemptDisabled> {
+        loop {
+            let status = self.status.read();
+            match &*status {
+                Status::Connecting(connecting) if connecting.result().is_some() => (),
+                _ => return status,
+            }
+            drop(status);
+
+            self.write_updated_status();
+        }
+    }
+
+    /// Ensures that the socket status is up to date and obtains a write lock on it.
+    fn write_updated_status(&self) -> RwLockWriteGuard<'_, Status, PreemptDisabled> {
+        let mut status = self.status.write();
+        status.update();
+        status
+    }
+
+    // Returns `None` to block the task and wait for the connection to be established, and returns
+    // `Some(_)` if blocking is not necessary or not allowed.
+    fn start_connect(&self, remote_addr: VsockSocketAddr) -> Option<Result<()>> {
+        let mut status = self.write_updated_status();
+
+        let init = match &*status {
+            Status::Init(init) => init.clone(),
+            Status::Connecting(_) if self.is_nonblocking() => {
+                return Some(Err(Error::with_message(Errno::EALREADY, "the socket is connecting")));
+            }
+            Status::Connecting(_) => return None,
+            Status::Listen(_) => {
+                return Some(Err(Error::with_message(Errno::EINVAL, "the socket is listened")));
+            }
+            Status::Connected(_) => {
+                return Some(Err(Error::with_message(Errno::EISCONN, "the socket is connected")));
+            }
+        };
+
+        let local_addr = match init.bound_addr() {
+            Some(addr) if addr == remote_addr => {
+                return Some(Err(Error::with_message(
+                    Errno::EINVAL,
+                    "try to connect to self is invalid",
+                )));
+            }
+            Some(addr) => addr,
+            None => {
+                if let Err(err) = init.bind(VsockSocketAddr::any_addr()) {
+                    return Some(Err(err));
+                }
+                init.bound_addr().unwrap()
+            }
+        };
+        // The error of the last connection request is cleared by the new one.
+        init.test_and_clear_error();
+
+        let connecting = Arc::new(Connecting::new(remote_addr, local_addr));
+        let vsockspace = VSOCK_GLOBAL.get().unwrap();
+        vsockspace.insert_connecting_socket(local_addr, connecting.clone());
+
+        // Send request
+        if let Err(err) = vsockspace.request(&connecting.info()) {
+            vsockspace.remove_connecting_socket(&local_addr);
+            return Some(Err(err));
+        }
+        *status = Status::Connecting(connecting);
+
+        if self.is_nonblocking() {
+            Some(Err(Error::with_message(Errno::EINPROGRESS, "the socket is connecting")))
+        } else {
+            None
+        }
+    }
+
+    fn check_connect(&self) -> Result<()> {
+        match &*self.read_updated_status() {
+            Status::Connecting(_) => {
+                return_errno_with_message!(Errno::EAGAIN, "the socket is connecting");
+            }
+            Status::Connected(_) => Ok(()),
+            Status::Init(init) => {
+                init.test_and_clear_error();
+                return_errno_with_message!(
+                    Errno::ECONNRESET,
+                    "the connection is reset by the peer"
+                );
+            }
+            Status::Listen(_) => {
+                return_errno_with_message!(Errno::EINVAL, "the socket is listened");
+            }
+        }
+    }
+
+    fn test_and_clear_error(&self) -> Option<Error> {
+        match &*self.read_updated_status() {
+            Status::Init(init) => init.test_and_clear_error(),
+            Status::Connecting(_) | Status::Listen(_) | Status::Connected(_) => None,
+        }
+    }
+
     fn try_accept(&self) -> Result<(Arc<dyn FileLike>, SocketAddr)> {
         let listen = match &*self.status.read() {
             Status::Listen(listen) => listen.clone(),
-            Status::Init(_) | Status::Connected(_) => {
+            Status::Init(_) | Status::Connecting(_) | Status::Connected(_) => {
                 return_errno_with_message!(Errno::EINVAL, "the socket is not listening");
             }
         };
@@ -82,10 +215,10 @@ impl VsockStreamSocket {
     }
 
     fn send(&self, reader: &mut dyn MultiRead, flags: SendRecvFlags) -> Result<usize> {
-        let inner = self.status.read();
+        let inner = self.read_updated_status();
         match &*inner {
             Status::Connected(connected) => connected.send(reader, flags),
-            Status::Init(_) | Status::Listen(_) => {
+            Status::Init(_) | Status::Connecting(_) | Status::Listen(_) => {
                 return_errno_with_message!(Errno::EINVAL, "the socket is not connected");
             }
         }
@@ -96,9 +229,9 @@ impl VsockStreamSocket {
         writer: &mut dyn MultiWrite,
         _flags: SendRecvFlags,
     ) -> Result<(usize, SocketAddr)> {
-        let connected = match &*self.status.read() {
+        let connected = match &*self.read_updated_status() {
             Status::Connected(connected) => connected.clone(),
-            Status::Init(_) | Status::Listen(_) => {
+            Status::Init(_) | Status::Connecting(_) | Status::Listen(_) => {
                 return_errno_with_message!(Errno::EINVAL, "the socket is not connected");
             }
         };
@@ -120,8 +253,9 @@ impl VsockStreamSocket {
 
 impl Pollable for VsockStreamSocket {
     fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
-        match &*self.status.read() {
+        match &*self.read_updated_status() {
             Status::Init(init) => init.poll(mask, poller),
+            Status::Connecting(connecting) => connecting.poll(mask, poller),
             Status::Listen(listen) => listen.poll(mask, poller),
             Status::Connected(connected) => connected.poll(mask, poller),
         }
@@ -141,10 +275,10 @@ impl SocketPrivate for VsockStreamSocket {
 impl Socket for VsockStreamSocket {
     fn bind(&self, sockaddr: SocketAddr) -> Result<()> {
         let addr = VsockSocketAddr::try_from(sockaddr)?;
-        let inner = self.status.read();
+        let inner = self.read_updated_status();
         match &*inner {
             Status::Init(init) => init.bind(addr),
-            Status::Listen(_) | Status::Connected(_) => {
+            Status::Connecting(_) | Status::Listen(_) | Status::Connected(_) => {
                 return_errno_with_message!(
                     Errno::EINVAL,
                     "cannot bind a listening or connected socket"
@@ -153,65 +287,26 @@ impl Socket for VsockStreamSocket {
         }
     }
 
-    // Since blocking mode is supported, there is no need to store the connecting status.
-    // TODO: Refactor when nonblocking mode is supported.
     fn connect(&self, sockaddr: SocketAddr) -> Result<()> {
-        let init = match &*self.status.read() {
-            Status::Init(init) => init.clone(),
-            Status::Listen(_) => {
-                return_errno_with_message!(Errno::EINVAL, "the socket is listened");
-            }
-            Status::Connected(_) => {
-                return_errno_with_message!(Errno::EINVAL, "the socket is connected");
-            }
-        };
         let remote_addr = VsockSocketAddr::try_from(sockaddr)?;
-        let local_addr = init.bound_addr();
-        if let Some(addr) = local_addr {
-            if addr == remote_addr {
-                return_errno_with_message!(Errno::EINVAL, "try to connect to self is invalid");
-            }
-        } else {
-            init.bind(VsockSocketAddr::any_addr())?;
-        }
-
-        let connecting = Arc::new(Connecting::new(remote_addr, init.bound_addr().unwrap()));
-        let vsockspace = VSOCK_GLOBAL.get().unwrap();
-        vsockspace.insert_connecting_socket(connecting.local_addr(), connecting.clone());
 
-        // Send request
-        vsockspace.request(&connecting.info()).unwrap();
-        // wait for response from driver
-        // TODO: Add timeout
-        let mut poller = Poller::new(None);
-        if !connecting
-            .poll(IoEvents::IN, Some(poller.as_handle_mut()))
-            .contains(IoEvents::IN)
-            && let Err(e) = poller.wait()
-        {
-            vsockspace
-                .remove_connecting_socket(&connecting.local_addr())
-                .unwrap();
-            return Err(e);
+        if let Some(result) = self.start_connect(remote_addr) {
+            return result;
         }
 
-        vsockspace
-            .remove_connecting_socket(&connecting.local_addr())
-            .unwrap();
-        let connected = Arc::new(Connected::from_connecting(connecting));
-        *self.status.write() = Status::Connected(connected.clone());
-        // move connecting socket map to connected sockmap
-        vsockspace.insert_connected_socket(connected.id(), connected);
-
-        Ok(())
+        // TODO: Add timeout
+        self.wait_events(IoEvents::OUT, None, || self.check_connect())
     }
 
     fn listen(&self, backlog: usize) -> Result<()> {
-        let init = match &*self.status.read() {
+        let init = match &*self.read_updated_status() {
             Status::Init(init) => init.clone(),
             Status::Listen(_) => {
                 return_errno_with_message!(Errno::EINVAL, "the socket is already listened");
             }
+            Status::Connecting(_) => {
+                return_errno_with_message!(Errno::EINVAL, "the socket is connecting");
+            }
             Status::Connected(_) => {
                 return_errno_with_message!(Errno::EISCONN, "the socket is already connected");
             }
@@ -237,9 +332,9 @@ impl Socket for VsockStreamSocket {
     }
 
     fn shutdown(&self, cmd: SockShutdownCmd) -> Result<()> {
-        match &*self.status.read() {
+        match &*self.read_updated_status() {
             Status::Connected(connected) => connected.shutdown(cmd),
-            Status::Init(_) | Status::Listen(_) => {
+            Status::Init(_) | Status::Connecting(_) | Status::Listen(_) => {
                 return_errno_with_message!(Errno::EINVAL, "the socket is not connected");
             }
         }
@@ -278,7 +373,8 @@ impl Socket for VsockStreamSocket {
             warn!("unsupported flags: {:?}", flags);
         }
 
-        let (received_bytes, _) = self.block_on(IoEvents::IN, || self.try_recv(writer, flags))?;
+        let (received_bytes, _) =
+            self.block_on_with_flags(IoEvents::IN, flags, || self.try_recv(writer, flags))?;
 
         // TODO: Receive control message
 
@@ -288,9 +384,10 @@ impl Socket for VsockStreamSocket {
     }
 
     fn addr(&self) -> Result<SocketAddr> {
-        let inner = self.status.read();
+        let inner = self.read_updated_status();
         let addr = match &*inner {
             Status::Init(init) => init.bound_addr(),
+            Status::Connecting(connecting) => Some(connecting.local_addr()),
             Status::Listen(listen) => Some(listen.addr()),
             Status::Connected(connected) => Some(connected.local_addr()),
         };
@@ -302,7 +399,7 @@ impl Socket for VsockStreamSocket {
     }
 
     fn peer_addr(&self) -> Result<SocketAddr> {
-        let inner = self.status.read();
+        let inner = self.read_updated_status();
         if let Status::Connected(connected) = &*inner {
             Ok(connected.peer_addr().into())
         } else {
@@ -310,6 +407,19 @@ impl Socket for VsockStreamSocket {
         }
     }
 
+    fn get_option(&self, option: &mut dyn SocketOption) -> Result<()> {
+        sock_option_mut!(match option {
+            socket_errors @ SocketError => {
+                socket_errors.set(self.test_and_clear_error());
+                return Ok(());
+            }
+            _ => (),
+        });
+
+        // TODO: Support other socket options
+        return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option to get is unknown")
+    }
+
     fn pseudo_path(&self) -> &Path {
         &self.pseudo_path
     }
@@ -319,12 +429,17 @@ impl Drop for VsockStreamSocket {
     fn drop(&mut self) {
         let vsockspace = VSOCK_GLOBAL.get().unwrap();
         let inner = self.status.get_mut();
+        inner.update();
         match inner {
             Status::Init(init) => {
                 if let Some(addr) = init.bound_addr() {
                     vsockspace.recycle_port(&addr.port);
                 }
             }
+            Status::Connecting(connecting) => {
+                vsockspace.remove_connecting_socket(&connecting.local_addr());
+                vsockspace.recycle_port(&connecting.local_addr().port);
+            }
             Status::Listen(listen) => {
                 vsockspace.recycle_port(&listen.addr().port);
                 vsockspace.remove_listen_socket(&listen.addr());
@@ -0,0 +1,245 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::collections::VecDeque;
+
+use aster_bigtcp::{
+    device::{self, DeviceCapabilities, Medium, NotifyDevice, WithDevice},
+    time::Instant,
+};
+use aster_softirq::BottomHalfDisabled;
+
+use crate::{events::IoEvents, prelude::*, process::signal::Pollee};
+
+/// The state shared by a TUN/TAP iface and its queues.
+pub(super) struct TunShared {
+    /// The packets written by user space, which have not been received by the iface.
+    rx_packets: SpinLock<VecDeque<Vec<u8>>, BottomHalfDisabled>,
+    /// The queues that are attached to the iface.
+    queues: SpinLock<Vec<Arc<TunQueue>>, BottomHalfDisabled>,
+}
+
+impl TunShared {
+    pub(super) fn new() -> Arc<Self> {
+        Arc::new(Self {
+            rx_packets: SpinLock::new(VecDeque::new()),
+            queues: SpinLock::new(Vec::new()),
+        })
+    }
+
+    /// Queues a packet written by user space, which will be received by the iface.
+    pub(super) fn push_rx_packet(&self, packet: Vec<u8>) {
+        self.rx_packets.lock().push_back(packet);
+    }
+
+    pub(super) fn attach_queue(&self, queue: Arc<TunQueue>) {
+        self.queues.lock().push(queue);
+    }
+
+    pub(super) fn detach_queue(&self, queue: &Arc<TunQueue>) {
+        self.queues
+            .lock()
+            .retain(|other| !Arc::ptr_eq(other, queue));
+    }
+
+    /// Sends a packet to one of the queues.
+    ///
+    /// The packets of the same flow are always sent to the same queue, so they are not reordered.
+    /// The packet is dropped if there are no queues or the queue is full.
+    fn send_packet(&self, packet: Vec<u8>, medium: Medium) {
+        let queue = {
+            let queues = self.queues.lock();
+            if queues.is_empty() {
+                return;
+            }
+            let hash = flow_hash(&packet, medium);
+            queues[hash as usize % queues.len()].clone()
+        };
+
+        queue.push(packet);
+    }
+}
+
+/// A queue of a TUN/TAP iface, which is an opened file of `/dev/net/tun`.
+pub(super) struct TunQueue {
+    /// The packets sent by the iface, which have not been read by user space.
+    packets: SpinLock<VecDeque<Vec<u8>>, BottomHalfDisabled>,
+    /// The pollee of the queue, which is readable if there are packets to read.
+    pollee: Pollee,
+}
+
+impl TunQueue {
+    pub(super) fn new() -> Arc<Self> {
+        Arc::new(Self {
+            packets: SpinLock::new(VecDeque::new()),
+            pollee: Pollee::new(),
+        })
+    }
+
+    fn push(&self, packet: Vec<u8>) {
+        {
+            let mut packets = self.packets.lock();
+            if packets.len() >= MAX_QUEUED_PACKETS {
+                return;
+            }
+            packets.push_back(packet);
+        }
+
+        self.pollee.notify(IoEvents::IN);
+    }
+
+    /// Takes the first packet in the queue.
+    pub(super) fn pop(&self) -> Option<Vec<u8>> {
+        let mut packets = self.packets.lock();
+        let packet = packets.pop_front()?;
+        if packets.is_empty() {
+            self.pollee.invalidate();
+        }
+        Some(packet)
+    }
+
+    pub(super) fn has_packets(&self) -> bool {
+        !self.packets.lock().is_empty()
+    }
+
+    pub(super) fn pollee(&self) -> &Pollee {
+        &self.pollee
+    }
+}
+
+/// The maximum number of packets in a queue.
+///
+/// This is the default value of `tx_queue_len` of TUN/TAP ifaces in Linux.
+const MAX_QUEUED_PACKETS: usize = 500;
+
+/// The device of a TUN/TAP iface.
+pub(super) struct TunDevice {
+    shared: Arc<TunShared>,
+    medium: Medium,
+}
+
+impl TunDevice {
+    pub(super) fn new(shared: Arc<TunShared>, medium: Medium) -> Self {
+        Self { shared, medium }
+    }
+}
+
+impl device::Device for TunDevice {
+    type RxToken<'a> = RxToken;
+    type TxToken<'a> = TxToken<'a>;
+
+    fn receive(&mut self, _timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        let packet = self.shared.rx_packets.lock().pop_front()?;
+        Some((RxToken(packet), TxToken(self)))
+    }
+
+    fn transmit(&mut self, _timestamp: Instant) -> Option<Self::TxToken<'_>> {
+        Some(TxToken(self))
+    }
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        let mut caps = DeviceCapabilities::default();
+        caps.medium = self.medium;
+        caps.max_transmission_unit = match self.medium {
+            Medium::Ethernet => MTU + ETHERNET_HEADER_LEN,
+            _ => MTU,
+        };
+        caps
+    }
+}
+
+impl NotifyDevice for TunDevice {
+    fn notify_poll_end(&mut self) {}
+}
+
+/// The default MTU of TUN/TAP ifaces.
+const MTU: usize = 1500;
+
+const ETHERNET_HEADER_LEN: usize = 14;
+
+pub(super) struct RxToken(Vec<u8>);
+
+impl device::RxToken for RxToken {
+    fn consume<R, F>(self, f: F) -> R
+    where
+        F: FnOnce(&[u8]) -> R,
+    {
+        f(&self.0)
+    }
+}
+
+pub(super) struct TxToken<'a>(&'a TunDevice);
+
+impl device::TxToken for TxToken<'_> {
+    fn consume<R, F>(self, len: usize, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        let mut packet = vec![0u8; len];
+        let res = f(&mut packet);
+        self.0.shared.send_packet(packet, self.0.medium);
+        res
+    }
+}
+
+pub(super) struct Wrapper(Mutex<TunDevice>);
+
+impl Wrapper {
+    pub(super) fn new(device: TunDevice) -> Self {
+        Self(Mutex::new(device))
+    }
+}
+
+impl WithDevice for Wrapper {
+    type Device = TunDevice;
+
+    fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Self::Device) -> R,
+    {
+        let mut device = self.0.lock();
+        f(&mut device)
+    }
+}
+
+/// Computes the hash of the flow that the packet belongs to.
+///
+/// The hash covers the IPv4 addresses, the protocol, and the ports (for TCP and UDP packets).
+/// Other packets are hashed to zero.
+fn flow_hash(packet: &[u8], medium: Medium) -> u32 {
+    const ETH_P_IP: &[u8] = &[0x08, 0x00];
+    const IPPROTO_TCP: u8 = 6;
+    const IPPROTO_UDP: u8 = 17;
+
+    let ip_packet = match medium {
+        Medium::Ethernet if packet.get(12..14) == Some(ETH_P_IP) => &packet[ETHERNET_HEADER_LEN..],
+        Medium::Ethernet => return 0,
+        _ => packet,
+    };
+
+    let Some(&version_ihl) = ip_packet.first() else {
+        return 0;
+    };
+    if version_ihl >> 4 != 4 || ip_packet.len() < 20 {
+        return 0;
+    }
+
+    let protocol = ip_packet[9];
+    let addrs = &ip_packet[12..20];
+    let header_len = ((version_ihl & 0x0F) as usize) * 4;
+    let ports = match protocol {
+        IPPROTO_TCP | IPPROTO_UDP => ip_packet.get(header_len..header_len + 4),
+        _ => None,
+    };
+
+    // The FNV-1a hash.
+    let mut hash = 0x811C_9DC5u32;
+    for byte in addrs
+        .iter()
+        .chain(core::iter::once(&protocol))
+        .chain(ports.unwrap_or_default())
+    {
+        hash ^= *byte as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    hash
+}
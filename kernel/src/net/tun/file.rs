@@ -0,0 +1,344 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::{IFNAMSIZ, TunFlags, TunIface, attach, device::TunQueue, release};
+use crate::{
+    current_userspace,
+    events::IoEvents,
+    fs::{
+        file::{FileIo, StatusFlags},
+        vfs::inode::InodeIo,
+    },
+    prelude::*,
+    process::signal::{PollHandle, Pollable},
+    util::ioctl::{RawIoctl, dispatch_ioctl},
+};
+
+/// An opened file of `/dev/net/tun`.
+///
+/// The file does nothing until it is attached to a TUN/TAP iface by `TUNSETIFF`. After that, it
+/// serves as a queue of the iface.
+pub struct TunFile {
+    state: Mutex<Option<AttachedState>>,
+}
+
+struct AttachedState {
+    tun_iface: Arc<TunIface>,
+    queue: Arc<TunQueue>,
+    /// Whether the queue is detached by `TUNSETQUEUE` with `IFF_DETACH_QUEUE`.
+    is_detached: bool,
+}
+
+impl TunFile {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(None),
+        }
+    }
+
+    /// Returns the iface and the queue if the file is attached to an iface.
+    fn attached(&self) -> Result<(Arc<TunIface>, Arc<TunQueue>)> {
+        let state = self.state.lock();
+        let Some(state) = state.as_ref().filter(|state| !state.is_detached) else {
+            return_errno_with_message!(
+                Errno::EBADFD,
+                "the file is not attached to a TUN/TAP iface"
+            );
+        };
+        Ok((state.tun_iface.clone(), state.queue.clone()))
+    }
+
+    fn set_iff(&self, ifreq: &mut CIfreq) -> Result<()> {
+        let mut state = self.state.lock();
+        if state.is_some() {
+            return_errno_with_message!(
+                Errno::EEXIST,
+                "the file is already attached to a TUN/TAP iface"
+            );
+        }
+
+        let name = ifreq.name()?;
+        let flags = TunFlags::from_bits(ifreq.flags)
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "the TUN/TAP flags are invalid"))?;
+        let (tun_iface, queue) = attach(name, flags)?;
+
+        *ifreq = CIfreq::new(tun_iface.iface().name(), tun_iface.flags());
+        *state = Some(AttachedState {
+            tun_iface,
+            queue,
+            is_detached: false,
+        });
+
+        Ok(())
+    }
+
+    fn set_queue(&self, ifreq: &CIfreq) -> Result<()> {
+        let mut state = self.state.lock();
+        let Some(state) = state.as_mut() else {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "the file is not attached to a TUN/TAP iface"
+            );
+        };
+
+        let flags = TunFlags::from_bits_truncate(ifreq.flags);
+        if flags.contains(TunFlags::IFF_ATTACH_QUEUE) {
+            if !state.is_detached {
+                return_errno_with_message!(Errno::EINVAL, "the queue is already attached");
+            }
+            state.tun_iface.shared().attach_queue(state.queue.clone());
+            state.is_detached = false;
+        } else if flags.contains(TunFlags::IFF_DETACH_QUEUE) {
+            if state.is_detached {
+                return_errno_with_message!(Errno::EINVAL, "the queue is already detached");
+            }
+            if !state.tun_iface.flags().contains(TunFlags::IFF_MULTI_QUEUE) {
+                return_errno_with_message!(
+                    Errno::EINVAL,
+                    "only the queues of multi-queue ifaces can be detached"
+                );
+            }
+            state.tun_iface.shared().detach_queue(&state.queue);
+            state.is_detached = true;
+        } else {
+            return_errno_with_message!(Errno::EINVAL, "the queue operation is invalid");
+        }
+
+        Ok(())
+    }
+
+    fn try_read(&self, writer: &mut VmWriter) -> Result<usize> {
+        let (tun_iface, queue) = self.attached()?;
+        let Some(packet) = queue.pop() else {
+            return_errno_with_message!(Errno::EAGAIN, "no packets are available");
+        };
+
+        let flags = tun_iface.flags();
+        let mut len = 0;
+
+        if !flags.contains(TunFlags::IFF_NO_PI) {
+            if writer.avail() < size_of::<CTunPi>() {
+                return_errno_with_message!(Errno::EINVAL, "the buffer is too small");
+            }
+            let is_truncated = writer.avail() < size_of::<CTunPi>() + packet.len();
+            let pi = CTunPi {
+                flags: if is_truncated { TUN_PKT_STRIP } else { 0 },
+                proto: packet_proto(&packet, flags).to_be(),
+            };
+            writer.write_val(&pi)?;
+            len += size_of::<CTunPi>();
+        }
+
+        // Like Linux, the packet is truncated if the buffer is too small.
+        len += writer.write_fallible(&mut VmReader::from(packet.as_slice()))?;
+        Ok(len)
+    }
+
+    fn write(&self, reader: &mut VmReader) -> Result<usize> {
+        let (tun_iface, _) = self.attached()?;
+        let len = reader.remain();
+
+        if !tun_iface.flags().contains(TunFlags::IFF_NO_PI) {
+            if len < size_of::<CTunPi>() {
+                return_errno_with_message!(Errno::EINVAL, "the packet information is truncated");
+            }
+            // The protocol is determined by the packet itself, so the information is ignored.
+            reader.skip(size_of::<CTunPi>());
+        }
+
+        let packet_len = reader.remain();
+        if packet_len == 0 || packet_len > MAX_PACKET_LEN {
+            return_errno_with_message!(Errno::EINVAL, "the packet length is invalid");
+        }
+        let mut packet = vec![0u8; packet_len];
+        reader.read_fallible(&mut VmWriter::from(packet.as_mut_slice()))?;
+
+        tun_iface.shared().push_rx_packet(packet);
+        tun_iface.iface().poll();
+
+        Ok(len)
+    }
+}
+
+impl Default for TunFile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for TunFile {
+    fn drop(&mut self) {
+        if let Some(state) = self.state.lock().take() {
+            release(&state.tun_iface, &state.queue);
+        }
+    }
+}
+
+impl Pollable for TunFile {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        let Ok((_, queue)) = self.attached() else {
+            return IoEvents::ERR;
+        };
+
+        queue.pollee().poll_with(mask, poller, || {
+            let mut events = IoEvents::OUT;
+            if queue.has_packets() {
+                events |= IoEvents::IN;
+            }
+            events
+        })
+    }
+}
+
+impl InodeIo for TunFile {
+    fn read_at(
+        &self,
+        _offset: usize,
+        writer: &mut VmWriter,
+        status_flags: StatusFlags,
+    ) -> Result<usize> {
+        // Check the state before waiting, so that reading an unattached file does not block.
+        self.attached()?;
+
+        if status_flags.contains(StatusFlags::O_NONBLOCK) {
+            self.try_read(writer)
+        } else {
+            self.wait_events(IoEvents::IN, None, || self.try_read(writer))
+        }
+    }
+
+    fn write_at(
+        &self,
+        _offset: usize,
+        reader: &mut VmReader,
+        _status_flags: StatusFlags,
+    ) -> Result<usize> {
+        self.write(reader)
+    }
+}
+
+impl FileIo for TunFile {
+    fn check_seekable(&self) -> Result<()> {
+        return_errno_with_message!(Errno::ESPIPE, "seek is not supported")
+    }
+
+    fn is_offset_aware(&self) -> bool {
+        false
+    }
+
+    fn ioctl(&self, raw_ioctl: RawIoctl) -> Result<i32> {
+        use ioctl_defs::*;
+
+        dispatch_ioctl!(match raw_ioctl {
+            cmd @ SetIff => {
+                let mut ifreq: CIfreq = current_userspace!().read_val(cmd.arg_addr())?;
+                self.set_iff(&mut ifreq)?;
+                current_userspace!().write_val(cmd.arg_addr(), &ifreq)?;
+            }
+            cmd @ GetIff => {
+                let (tun_iface, _) = self.attached()?;
+                let ifreq = CIfreq::new(tun_iface.iface().name(), tun_iface.flags());
+                current_userspace!().write_val(cmd.arg_addr(), &ifreq)?;
+            }
+            cmd @ SetPersist => {
+                let (tun_iface, _) = self.attached()?;
+                tun_iface.set_persistent(cmd.get() != 0);
+            }
+            cmd @ GetFeatures => {
+                let features = TunFlags::SUPPORTED_FLAGS.bits() as u32;
+                cmd.write(&features)?;
+            }
+            cmd @ SetQueue => {
+                let ifreq: CIfreq = current_userspace!().read_val(cmd.arg_addr())?;
+                self.set_queue(&ifreq)?;
+            }
+            _ => return_errno_with_message!(
+                Errno::ENOTTY,
+                "the ioctl command is not supported by TUN/TAP files"
+            ),
+        });
+
+        Ok(0)
+    }
+}
+
+/// The maximum length of the packets written by user space.
+const MAX_PACKET_LEN: usize = 65535;
+
+/// `struct ifreq` with the name and the flags.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/if.h#L234>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CIfreq {
+    name: [u8; IFNAMSIZ],
+    flags: u16,
+    /// The padding to the size of the union in `struct ifreq`.
+    _pad: [u8; 22],
+}
+
+impl CIfreq {
+    fn new(name: &str, flags: TunFlags) -> Self {
+        let mut ifreq = Self::new_zeroed();
+        ifreq.name[..name.len()].copy_from_slice(name.as_bytes());
+        ifreq.flags = flags.bits();
+        ifreq
+    }
+
+    fn name(&self) -> Result<&str> {
+        let len = self
+            .name
+            .iter()
+            .position(|byte| *byte == 0)
+            .unwrap_or(IFNAMSIZ)
+            .min(IFNAMSIZ - 1);
+        core::str::from_utf8(&self.name[..len])
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the iface name is invalid"))
+    }
+}
+
+/// `struct tun_pi`, which precedes each packet unless `IFF_NO_PI` is specified.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/if_tun.h#L91>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CTunPi {
+    flags: u16,
+    /// The protocol of the packet (in network byte order).
+    proto: u16,
+}
+
+/// The flag in [`CTunPi`] indicating that the packet is truncated.
+const TUN_PKT_STRIP: u16 = 0x0001;
+
+/// Returns the protocol (i.e., the EtherType) of the packet.
+fn packet_proto(packet: &[u8], flags: TunFlags) -> u16 {
+    const ETH_P_IP: u16 = 0x0800;
+    const ETH_P_IPV6: u16 = 0x86DD;
+
+    if flags.contains(TunFlags::IFF_TAP) {
+        return match packet.get(12..14) {
+            Some(&[high, low]) => u16::from_be_bytes([high, low]),
+            _ => 0,
+        };
+    }
+
+    match packet.first().map(|byte| byte >> 4) {
+        Some(4) => ETH_P_IP,
+        Some(6) => ETH_P_IPV6,
+        _ => 0,
+    }
+}
+
+mod ioctl_defs {
+    use crate::util::ioctl::{InData, OutData, PassByVal, ioc};
+
+    // Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/if_tun.h#L36>
+
+    // `TUNSETIFF`, `TUNGETIFF`, and `TUNSETQUEUE` are declared with `int` or `unsigned int`, but
+    // they actually take `struct ifreq`.
+    pub(super) type SetIff      = ioc!(TUNSETIFF,      b'T', 202, InData<i32>);
+    pub(super) type SetPersist  = ioc!(TUNSETPERSIST,  b'T', 203, InData<i32, PassByVal>);
+    pub(super) type GetFeatures = ioc!(TUNGETFEATURES, b'T', 207, OutData<u32>);
+    pub(super) type GetIff      = ioc!(TUNGETIFF,      b'T', 210, OutData<u32>);
+    pub(super) type SetQueue    = ioc!(TUNSETQUEUE,    b'T', 217, InData<i32>);
+}
@@ -0,0 +1,231 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! TUN/TAP ifaces.
+//!
+//! A TUN/TAP iface is created by the `TUNSETIFF` ioctl on an opened file of `/dev/net/tun`. The
+//! packets sent by the iface can be read from the file, and the packets written to the file are
+//! received by the iface. A TUN iface works with IP packets, while a TAP iface works with
+//! Ethernet frames.
+//!
+//! If an iface is created with `IFF_MULTI_QUEUE`, multiple files can be attached to the iface,
+//! each of which is a queue. The packets sent by the iface are distributed among the queues
+//! according to their flows.
+
+mod device;
+mod file;
+
+use alloc::borrow::ToOwned;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use aster_bigtcp::{
+    device::Medium,
+    iface::{EtherIface, InterfaceFlags, InterfaceType, IpIface},
+};
+
+use self::device::{TunDevice, TunQueue, TunShared, Wrapper};
+use super::iface::{
+    Iface, PollScheduler, add_iface, alloc_iface_name, iter_all_ifaces, random_ether_addr,
+    remove_iface,
+};
+use crate::prelude::*;
+
+pub use file::TunFile;
+
+bitflags! {
+    /// The flags of a TUN/TAP iface.
+    pub(super) struct TunFlags: u16 {
+        const IFF_TUN          = 0x0001;
+        const IFF_TAP          = 0x0002;
+        const IFF_MULTI_QUEUE  = 0x0100;
+        const IFF_ATTACH_QUEUE = 0x0200;
+        const IFF_DETACH_QUEUE = 0x0400;
+        const IFF_PERSIST      = 0x0800;
+        const IFF_NO_PI        = 0x1000;
+        const IFF_ONE_QUEUE    = 0x2000;
+        const IFF_VNET_HDR     = 0x4000;
+    }
+}
+
+impl TunFlags {
+    /// The flags that are remembered by the iface.
+    const IFACE_FLAGS: Self = Self::IFF_TUN
+        .union(Self::IFF_TAP)
+        .union(Self::IFF_MULTI_QUEUE)
+        .union(Self::IFF_NO_PI);
+
+    /// The flags that are supported by `TUNSETIFF`.
+    const SUPPORTED_FLAGS: Self = Self::IFACE_FLAGS.union(Self::IFF_ONE_QUEUE);
+}
+
+/// The TUN/TAP ifaces, indexed by their names.
+static TUN_IFACES: Mutex<BTreeMap<String, Arc<TunIface>>> = Mutex::new(BTreeMap::new());
+
+/// A TUN/TAP iface.
+struct TunIface {
+    iface: Arc<Iface>,
+    flags: TunFlags,
+    shared: Arc<TunShared>,
+    /// The number of the files that are attached to the iface.
+    ///
+    /// The detached queues (see `IFF_DETACH_QUEUE`) are also counted. This field is only modified
+    /// with [`TUN_IFACES`] locked.
+    num_files: AtomicUsize,
+    is_persistent: AtomicBool,
+}
+
+impl TunIface {
+    fn new(name: String, flags: TunFlags) -> Arc<Self> {
+        let shared = TunShared::new();
+
+        let iface = if flags.contains(TunFlags::IFF_TAP) {
+            let device = TunDevice::new(shared.clone(), Medium::Ethernet);
+            let iface_flags = InterfaceFlags::UP
+                | InterfaceFlags::BROADCAST
+                | InterfaceFlags::RUNNING
+                | InterfaceFlags::MULTICAST
+                | InterfaceFlags::LOWER_UP;
+            EtherIface::new(
+                Wrapper::new(device),
+                random_ether_addr(),
+                None,
+                None,
+                name,
+                PollScheduler::new(),
+                iface_flags,
+            ) as Arc<Iface>
+        } else {
+            let device = TunDevice::new(shared.clone(), Medium::Ip);
+            let iface_flags = InterfaceFlags::UP
+                | InterfaceFlags::POINTOPOINT
+                | InterfaceFlags::RUNNING
+                | InterfaceFlags::NOARP
+                | InterfaceFlags::MULTICAST
+                | InterfaceFlags::LOWER_UP;
+            IpIface::new(
+                Wrapper::new(device),
+                None,
+                name,
+                PollScheduler::new(),
+                InterfaceType::NONE,
+                iface_flags,
+            ) as Arc<Iface>
+        };
+
+        Arc::new(Self {
+            iface,
+            flags,
+            shared,
+            num_files: AtomicUsize::new(0),
+            is_persistent: AtomicBool::new(false),
+        })
+    }
+
+    fn iface(&self) -> &Arc<Iface> {
+        &self.iface
+    }
+
+    fn flags(&self) -> TunFlags {
+        if self.is_persistent.load(Ordering::Relaxed) {
+            self.flags | TunFlags::IFF_PERSIST
+        } else {
+            self.flags
+        }
+    }
+
+    fn shared(&self) -> &Arc<TunShared> {
+        &self.shared
+    }
+
+    fn set_persistent(&self, is_persistent: bool) {
+        self.is_persistent.store(is_persistent, Ordering::Relaxed);
+    }
+}
+
+/// Attaches a new queue to the TUN/TAP iface with the name, creating the iface if necessary.
+///
+/// If the name contains `%d`, the name is a template, where `%d` is replaced by the first unused
+/// number.
+fn attach(name: &str, flags: TunFlags) -> Result<(Arc<TunIface>, Arc<TunQueue>)> {
+    if flags.contains(TunFlags::IFF_TUN) == flags.contains(TunFlags::IFF_TAP) {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "exactly one of IFF_TUN and IFF_TAP must be specified"
+        );
+    }
+    if !TunFlags::SUPPORTED_FLAGS.contains(flags) {
+        return_errno_with_message!(Errno::EINVAL, "the TUN/TAP flags are not supported");
+    }
+    let flags = flags & TunFlags::IFACE_FLAGS;
+
+    let mut tun_ifaces = TUN_IFACES.lock();
+
+    let tun_iface = if let Some(tun_iface) = tun_ifaces.get(name) {
+        if tun_iface.flags & TunFlags::IFACE_FLAGS != flags {
+            return_errno_with_message!(Errno::EINVAL, "the TUN/TAP flags mismatch");
+        }
+        if !flags.contains(TunFlags::IFF_MULTI_QUEUE)
+            && tun_iface.num_files.load(Ordering::Relaxed) > 0
+        {
+            return_errno_with_message!(Errno::EBUSY, "the TUN/TAP iface has only one queue");
+        }
+        tun_iface.clone()
+    } else {
+        check_permission()?;
+
+        let name = if name.is_empty() {
+            let template = if flags.contains(TunFlags::IFF_TAP) {
+                "tap%d"
+            } else {
+                "tun%d"
+            };
+            alloc_iface_name(template)?
+        } else if name.contains("%d") {
+            alloc_iface_name(name)?
+        } else if iter_all_ifaces().any(|iface| iface.name() == name) {
+            return_errno_with_message!(Errno::EINVAL, "the iface is not a TUN/TAP iface");
+        } else {
+            name.to_owned()
+        };
+
+        let tun_iface = TunIface::new(name.clone(), flags);
+        add_iface(tun_iface.iface().clone())?;
+        tun_ifaces.insert(name, tun_iface.clone());
+        tun_iface
+    };
+
+    tun_iface.num_files.fetch_add(1, Ordering::Relaxed);
+    let queue = TunQueue::new();
+    tun_iface.shared().attach_queue(queue.clone());
+
+    Ok((tun_iface, queue))
+}
+
+/// Releases a file attached to the TUN/TAP iface when the file is closed.
+///
+/// The iface is removed if no files are attached to it and it is not persistent.
+fn release(tun_iface: &Arc<TunIface>, queue: &Arc<TunQueue>) {
+    tun_iface.shared().detach_queue(queue);
+
+    let mut tun_ifaces = TUN_IFACES.lock();
+    let num_files = tun_iface.num_files.fetch_sub(1, Ordering::Relaxed) - 1;
+    if num_files == 0 && !tun_iface.is_persistent.load(Ordering::Relaxed) {
+        tun_ifaces.remove(tun_iface.iface().name());
+        remove_iface(tun_iface.iface());
+    }
+}
+
+const IFNAMSIZ: usize = 16;
+
+fn check_permission() -> Result<()> {
+    use crate::process::{credentials::capabilities::CapSet, posix_thread::AsPosixThread};
+
+    let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+    if !credentials.effective_capset().contains(CapSet::NET_ADMIN) {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "creating TUN/TAP ifaces requires the CAP_NET_ADMIN capability"
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,62 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_bigtcp::wire::{Ipv4Address, Ipv4Cidr};
+
+use super::noise::Key;
+use crate::prelude::*;
+
+/// The table that maps the allowed IP ranges to the peers.
+///
+/// When a packet is sent, its destination address selects the peer with the longest matching
+/// prefix. When a packet is received from a peer, its source address must select the same peer.
+pub(super) struct AllowedIps {
+    /// The IP ranges and the public keys of the peers.
+    ///
+    /// The number of the entries is expected to be small, so a linear search is good enough.
+    entries: Vec<(Ipv4Cidr, Key)>,
+}
+
+impl AllowedIps {
+    pub(super) const fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Maps the IP range to the peer.
+    ///
+    /// Like Linux, the host bits of the address are cleared, and the IP range is removed from the
+    /// peer that it was previously mapped to.
+    pub(super) fn insert(&mut self, cidr: Ipv4Cidr, peer: &Key) {
+        let cidr = cidr.network();
+
+        if let Some(entry) = self.entries.iter_mut().find(|(other, _)| *other == cidr) {
+            entry.1 = *peer;
+        } else {
+            self.entries.push((cidr, *peer));
+        }
+    }
+
+    /// Removes all the IP ranges of the peer.
+    pub(super) fn remove_peer(&mut self, peer: &Key) {
+        self.entries.retain(|(_, other)| other != peer);
+    }
+
+    /// Returns the peer with the longest IP range that contains the address.
+    pub(super) fn lookup(&self, addr: &Ipv4Address) -> Option<&Key> {
+        self.entries
+            .iter()
+            .filter(|(cidr, _)| cidr.contains_addr(addr))
+            .max_by_key(|(cidr, _)| cidr.prefix_len())
+            .map(|(_, peer)| peer)
+    }
+
+    /// Returns the IP ranges of the peer.
+    pub(super) fn peer_cidrs(&self, peer: &Key) -> Vec<Ipv4Cidr> {
+        self.entries
+            .iter()
+            .filter(|(_, other)| other == peer)
+            .map(|(cidr, _)| *cidr)
+            .collect()
+    }
+}
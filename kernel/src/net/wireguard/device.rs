@@ -0,0 +1,328 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::collections::VecDeque;
+use core::net::SocketAddrV4;
+
+use aster_bigtcp::{
+    device::{self, DeviceCapabilities, Medium, NotifyDevice, WithDevice},
+    time::Instant,
+    wire::Ipv4Address,
+};
+use aster_softirq::BottomHalfDisabled;
+use spin::Once;
+
+use super::{DeviceConfig, DeviceUpdate, state::DeviceState};
+use crate::{
+    events::{IoEvents, Observer},
+    net::{
+        iface::Iface,
+        socket::{
+            Socket,
+            ip::{DatagramSocket, IpFamily},
+            util::{MessageHeader, SendRecvFlags, SocketAddr},
+        },
+    },
+    prelude::*,
+    process::signal::{PollAdaptor, Pollable},
+    thread::work_queue::{WorkPriority, submit_work_item, work_item::WorkItem},
+};
+
+/// The state shared by a WireGuard iface and its UDP socket.
+pub(super) struct WgShared {
+    /// The packets sent by the iface, which have not been encrypted.
+    tx_packets: SpinLock<VecDeque<Vec<u8>>, BottomHalfDisabled>,
+    /// The packets decrypted from the peers, which have not been received by the iface.
+    rx_packets: SpinLock<VecDeque<Vec<u8>>, BottomHalfDisabled>,
+    /// The work item that encrypts the packets sent by the iface.
+    tx_work: Arc<WorkItem>,
+    /// The work item that decrypts the datagrams received by the UDP socket.
+    ///
+    /// The cryptographic operations are too expensive to be done while an iface is being polled,
+    /// so they are done in work items.
+    rx_work: Arc<WorkItem>,
+    iface: Once<Weak<Iface>>,
+    state: Mutex<DeviceState>,
+    socket: Mutex<Option<WgSocket>>,
+}
+
+/// The UDP socket of a WireGuard iface.
+struct WgSocket {
+    socket: Arc<DatagramSocket>,
+    port: u16,
+    /// The adaptor that submits the work item to receive the datagrams when the socket becomes
+    /// readable.
+    _poller: PollAdaptor<RxObserver>,
+}
+
+struct RxObserver(Arc<WorkItem>);
+
+impl Observer<IoEvents> for RxObserver {
+    fn on_events(&self, _events: &IoEvents) {
+        submit_work_item(self.0.clone(), WorkPriority::High);
+    }
+}
+
+impl WgShared {
+    /// Creates the shared state with a UDP socket bound to a random port.
+    pub(super) fn new() -> Result<Arc<Self>> {
+        let shared = Arc::new_cyclic(|shared: &Weak<Self>| {
+            let tx_shared = shared.clone();
+            let tx_work = WorkItem::new(Box::new(move || {
+                if let Some(shared) = tx_shared.upgrade() {
+                    shared.do_tx();
+                }
+            }));
+            let rx_shared = shared.clone();
+            let rx_work = WorkItem::new(Box::new(move || {
+                if let Some(shared) = rx_shared.upgrade() {
+                    shared.do_rx();
+                }
+            }));
+
+            Self {
+                tx_packets: SpinLock::new(VecDeque::new()),
+                rx_packets: SpinLock::new(VecDeque::new()),
+                tx_work,
+                rx_work,
+                iface: Once::new(),
+                state: Mutex::new(DeviceState::new()),
+                socket: Mutex::new(None),
+            }
+        });
+
+        *shared.socket.lock() = Some(shared.bind_socket(0)?);
+
+        Ok(shared)
+    }
+
+    /// Binds the shared state to its iface.
+    pub(super) fn bind_iface(&self, iface: &Arc<Iface>) {
+        self.iface.call_once(|| Arc::downgrade(iface));
+    }
+
+    /// Closes the UDP socket when the iface is deleted.
+    pub(super) fn close(&self) {
+        *self.socket.lock() = None;
+    }
+
+    pub(super) fn config(&self) -> DeviceConfig {
+        let mut config = self.state.lock().config();
+        config.listen_port = self.socket.lock().as_ref().map_or(0, |socket| socket.port);
+        config
+    }
+
+    pub(super) fn update(&self, mut update: DeviceUpdate) -> Result<()> {
+        let mut socket = self.socket.lock();
+
+        // The port is changed first, since it is the only change that can fail.
+        if let Some(port) = update.listen_port.take() {
+            let is_same_port = socket.as_ref().is_some_and(|socket| socket.port == port);
+            if !is_same_port {
+                // Drop the old socket first, so its port can be reused.
+                *socket = None;
+                *socket = Some(self.bind_socket(port)?);
+            }
+        }
+
+        self.state.lock().update(update);
+
+        Ok(())
+    }
+
+    fn bind_socket(&self, port: u16) -> Result<WgSocket> {
+        let socket = DatagramSocket::new(true, IpFamily::Ipv4);
+        socket.bind(SocketAddr::IPv4(Ipv4Address::UNSPECIFIED, port))?;
+        let SocketAddr::IPv4(_, port) = socket.addr()? else {
+            unreachable!("the address of an IPv4 socket must be an IPv4 address");
+        };
+
+        let mut poller = PollAdaptor::with_observer(RxObserver(self.rx_work.clone()));
+        socket.poll(IoEvents::IN, Some(poller.as_handle_mut()));
+
+        Ok(WgSocket {
+            socket,
+            port,
+            _poller: poller,
+        })
+    }
+
+    fn udp_socket(&self) -> Option<Arc<DatagramSocket>> {
+        self.socket
+            .lock()
+            .as_ref()
+            .map(|socket| socket.socket.clone())
+    }
+
+    /// Queues a packet sent by the iface, which will be encrypted and sent to a peer.
+    fn push_tx_packet(&self, packet: Vec<u8>) {
+        {
+            let mut tx_packets = self.tx_packets.lock();
+            if tx_packets.len() >= MAX_QUEUED_PACKETS {
+                return;
+            }
+            tx_packets.push_back(packet);
+        }
+
+        submit_work_item(self.tx_work.clone(), WorkPriority::High);
+    }
+
+    fn do_tx(&self) {
+        loop {
+            let Some(packet) = self.tx_packets.lock().pop_front() else {
+                break;
+            };
+            let Some((endpoint, datagrams)) = self.state.lock().send_packet(packet) else {
+                continue;
+            };
+            self.send_datagrams(endpoint, datagrams);
+        }
+    }
+
+    fn do_rx(&self) {
+        let Some(socket) = self.udp_socket() else {
+            return;
+        };
+
+        let mut buf = vec![0u8; MAX_DATAGRAM_SIZE];
+        let mut has_packets = false;
+
+        loop {
+            let mut writer = VmWriter::from(buf.as_mut_slice()).to_fallible();
+            let Ok((len, header)) = socket.recvmsg(&mut writer, SendRecvFlags::empty()) else {
+                break;
+            };
+            let Some(SocketAddr::IPv4(addr, port)) = header.addr() else {
+                continue;
+            };
+            let src = SocketAddrV4::new(*addr, *port);
+
+            let (replies, packet) = self.state.lock().handle_datagram(&mut buf[..len], src);
+            self.send_datagrams(src, replies);
+
+            if let Some(packet) = packet {
+                let mut rx_packets = self.rx_packets.lock();
+                if rx_packets.len() < MAX_QUEUED_PACKETS {
+                    rx_packets.push_back(packet);
+                    has_packets = true;
+                }
+            }
+        }
+
+        if !has_packets {
+            return;
+        }
+        if let Some(iface) = self.iface.get().and_then(Weak::upgrade) {
+            iface.poll();
+        }
+    }
+
+    fn send_datagrams(&self, endpoint: SocketAddrV4, datagrams: Vec<Vec<u8>>) {
+        if datagrams.is_empty() {
+            return;
+        }
+        let Some(socket) = self.udp_socket() else {
+            return;
+        };
+
+        for datagram in datagrams {
+            let mut reader = VmReader::from(datagram.as_slice()).to_fallible();
+            let addr = SocketAddr::IPv4(*endpoint.ip(), endpoint.port());
+            let header = MessageHeader::new(Some(addr), Vec::new());
+            // The datagram is dropped if it cannot be sent, just like a lost packet.
+            let _ = socket.sendmsg(&mut reader, header, SendRecvFlags::empty());
+        }
+    }
+}
+
+/// The maximum number of packets in a queue.
+const MAX_QUEUED_PACKETS: usize = 1000;
+
+/// The maximum size of a UDP datagram.
+const MAX_DATAGRAM_SIZE: usize = 65536;
+
+/// The device of a WireGuard iface.
+pub(super) struct WgDevice {
+    shared: Arc<WgShared>,
+}
+
+impl WgDevice {
+    pub(super) fn new(shared: Arc<WgShared>) -> Self {
+        Self { shared }
+    }
+}
+
+impl device::Device for WgDevice {
+    type RxToken<'a> = RxToken;
+    type TxToken<'a> = TxToken<'a>;
+
+    fn receive(&mut self, _timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        let packet = self.shared.rx_packets.lock().pop_front()?;
+        Some((RxToken(packet), TxToken(self)))
+    }
+
+    fn transmit(&mut self, _timestamp: Instant) -> Option<Self::TxToken<'_>> {
+        Some(TxToken(self))
+    }
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        let mut caps = DeviceCapabilities::default();
+        caps.medium = Medium::Ip;
+        caps.max_transmission_unit = MTU;
+        caps
+    }
+}
+
+impl NotifyDevice for WgDevice {
+    fn notify_poll_end(&mut self) {}
+}
+
+/// The default MTU of WireGuard ifaces.
+///
+/// This is the Ethernet MTU minus the overhead of the IPv6 header, the UDP header, and the
+/// WireGuard transport header, so the encrypted packets are not fragmented.
+const MTU: usize = 1420;
+
+pub(super) struct RxToken(Vec<u8>);
+
+impl device::RxToken for RxToken {
+    fn consume<R, F>(self, f: F) -> R
+    where
+        F: FnOnce(&[u8]) -> R,
+    {
+        f(&self.0)
+    }
+}
+
+pub(super) struct TxToken<'a>(&'a WgDevice);
+
+impl device::TxToken for TxToken<'_> {
+    fn consume<R, F>(self, len: usize, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        let mut packet = vec![0u8; len];
+        let res = f(&mut packet);
+        self.0.shared.push_tx_packet(packet);
+        res
+    }
+}
+
+pub(super) struct Wrapper(Mutex<WgDevice>);
+
+impl Wrapper {
+    pub(super) fn new(device: WgDevice) -> Self {
+        Self(Mutex::new(device))
+    }
+}
+
+impl WithDevice for Wrapper {
+    type Device = WgDevice;
+
+    fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Self::Device) -> R,
+    {
+        let mut device = self.0.lock();
+        f(&mut device)
+    }
+}
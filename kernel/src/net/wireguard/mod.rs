@@ -0,0 +1,185 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! WireGuard ifaces.
+//!
+//! A WireGuard iface is created via rtnetlink with the link kind `wireguard`, and is configured
+//! via the `wireguard` generic netlink family. The packets sent by the iface are encrypted and
+//! sent to the peers over UDP, and the datagrams received from the peers are decrypted and
+//! received by the iface.
+//!
+//! The following features are not supported for now:
+//!  - IPv6 packets and IPv6 endpoints;
+//!  - Cookie replies, which protect the iface under load;
+//!  - Timers. A handshake is initiated (or retried) only when a packet is sent, and persistent
+//!    keepalive messages are not sent.
+//!
+//! Reference: <https://www.wireguard.com/protocol/>.
+
+mod allowed_ips;
+mod device;
+mod noise;
+mod peer;
+mod state;
+
+use core::{net::SocketAddrV4, time::Duration};
+
+use aster_bigtcp::{
+    iface::{InterfaceFlags, InterfaceType, IpIface},
+    wire::Ipv4Cidr,
+};
+
+use self::device::{WgDevice, WgShared, Wrapper};
+use super::{
+    iface::{Iface, PollScheduler, add_iface, remove_iface},
+    link::alloc_name,
+};
+use crate::prelude::*;
+
+/// The WireGuard ifaces, indexed by the indexes of their ifaces.
+static WIREGUARDS: Mutex<BTreeMap<u32, WireGuard>> = Mutex::new(BTreeMap::new());
+
+struct WireGuard {
+    iface: Arc<Iface>,
+    shared: Arc<WgShared>,
+}
+
+/// The configuration of a WireGuard iface.
+#[derive(Debug)]
+pub struct DeviceConfig {
+    pub private_key: Option<[u8; 32]>,
+    pub public_key: Option<[u8; 32]>,
+    pub listen_port: u16,
+    pub fwmark: u32,
+    pub peers: Vec<PeerConfig>,
+}
+
+/// The configuration of a peer of a WireGuard iface.
+#[derive(Debug)]
+pub struct PeerConfig {
+    pub public_key: [u8; 32],
+    pub preshared_key: [u8; 32],
+    pub endpoint: Option<SocketAddrV4>,
+    pub persistent_keepalive_interval: u16,
+    /// The time of the last handshake since the Unix epoch, or zero if there is no handshake.
+    pub last_handshake_time: Duration,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub allowed_ips: Vec<Ipv4Cidr>,
+}
+
+/// The changes to the configuration of a WireGuard iface.
+#[derive(Debug, Default)]
+pub struct DeviceUpdate {
+    pub private_key: Option<[u8; 32]>,
+    pub listen_port: Option<u16>,
+    pub fwmark: Option<u32>,
+    /// Whether to remove all the existing peers before the peers are updated.
+    pub replace_peers: bool,
+    pub peers: Vec<PeerUpdate>,
+}
+
+/// The changes to a peer of a WireGuard iface.
+#[derive(Debug)]
+pub struct PeerUpdate {
+    pub public_key: [u8; 32],
+    /// Whether to remove the peer.
+    pub remove: bool,
+    /// Whether to update the peer only if it exists, instead of creating it.
+    pub update_only: bool,
+    pub preshared_key: Option<[u8; 32]>,
+    pub endpoint: Option<SocketAddrV4>,
+    pub persistent_keepalive_interval: Option<u16>,
+    /// Whether to remove all the existing allowed IP ranges before the new ones are added.
+    pub replace_allowed_ips: bool,
+    pub allowed_ips: Vec<Ipv4Cidr>,
+}
+
+impl PeerUpdate {
+    /// Creates an update that creates the peer if it does not exist and changes nothing else.
+    pub fn new(public_key: [u8; 32]) -> Self {
+        Self {
+            public_key,
+            remove: false,
+            update_only: false,
+            preshared_key: None,
+            endpoint: None,
+            persistent_keepalive_interval: None,
+            replace_allowed_ips: false,
+            allowed_ips: Vec::new(),
+        }
+    }
+}
+
+/// Creates a WireGuard iface.
+///
+/// If the name is not specified, it is allocated from the template `wg%d`. This function returns
+/// the index of the iface.
+pub fn new_wireguard(name: Option<&str>) -> Result<u32> {
+    let mut wireguards = WIREGUARDS.lock();
+
+    let name = alloc_name(name, "wg%d")?;
+    let shared = WgShared::new()?;
+
+    let flags = InterfaceFlags::UP
+        | InterfaceFlags::POINTOPOINT
+        | InterfaceFlags::RUNNING
+        | InterfaceFlags::NOARP
+        | InterfaceFlags::LOWER_UP;
+    let iface = IpIface::new(
+        Wrapper::new(WgDevice::new(shared.clone())),
+        None,
+        name,
+        PollScheduler::new(),
+        InterfaceType::NONE,
+        flags,
+    ) as Arc<Iface>;
+    shared.bind_iface(&iface);
+    if let Err(err) = add_iface(iface.clone()) {
+        shared.close();
+        return Err(err);
+    }
+
+    let index = iface.index();
+    wireguards.insert(index, WireGuard { iface, shared });
+
+    Ok(index)
+}
+
+/// Deletes a WireGuard iface.
+pub fn delete_wireguard(index: u32) -> Result<()> {
+    let Some(wireguard) = WIREGUARDS.lock().remove(&index) else {
+        return_errno_with_message!(Errno::EOPNOTSUPP, "the iface is not a WireGuard iface");
+    };
+
+    wireguard.shared.close();
+    remove_iface(&wireguard.iface);
+
+    Ok(())
+}
+
+/// Returns whether the iface is a WireGuard iface.
+pub fn is_wireguard(index: u32) -> bool {
+    WIREGUARDS.lock().contains_key(&index)
+}
+
+/// Returns the configuration of a WireGuard iface.
+pub fn get_config(index: u32) -> Result<DeviceConfig> {
+    let shared = get_shared(index)?;
+    Ok(shared.config())
+}
+
+/// Changes the configuration of a WireGuard iface.
+pub fn set_config(index: u32, update: DeviceUpdate) -> Result<()> {
+    let shared = get_shared(index)?;
+    shared.update(update)
+}
+
+fn get_shared(index: u32) -> Result<Arc<WgShared>> {
+    let wireguards = WIREGUARDS.lock();
+
+    let Some(wireguard) = wireguards.get(&index) else {
+        return_errno_with_message!(Errno::EOPNOTSUPP, "the iface is not a WireGuard iface");
+    };
+
+    Ok(wireguard.shared.clone())
+}
@@ -0,0 +1,651 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The cryptographic part of the WireGuard protocol.
+//!
+//! The handshake follows `Noise_IKpsk2_25519_ChaChaPoly_BLAKE2s`. It derives a keypair, which
+//! protects the transport data in both directions until the keypair expires.
+//!
+//! Reference: <https://www.wireguard.com/papers/wireguard.pdf>.
+
+use core::{mem::offset_of, time::Duration};
+
+use align_ext::AlignExt;
+
+use crate::{
+    prelude::*,
+    time::clocks::RealTimeCoarseClock,
+    util::{
+        crypto::{
+            api::{Aead, AeadAlg},
+            blake2s::{Blake2s, blake2s, hmac_blake2s},
+            chacha20poly1305::{CHACHA20_POLY1305_NONCE_SIZE, CHACHA20_POLY1305_TAG_SIZE},
+            x25519::{X25519_KEY_SIZE, x25519, x25519_public_key},
+        },
+        random::getrandom,
+    },
+};
+
+/// A Curve25519 key, or a symmetric key of the same size.
+pub(super) type Key = [u8; X25519_KEY_SIZE];
+
+const CONSTRUCTION: &[u8] = b"Noise_IKpsk2_25519_ChaChaPoly_BLAKE2s";
+const IDENTIFIER: &[u8] = b"WireGuard v1 zx2c4 Jason@zx2c4.com";
+const LABEL_MAC1: &[u8] = b"mac1----";
+
+const MESSAGE_INITIATION: u32 = 1;
+const MESSAGE_RESPONSE: u32 = 2;
+const MESSAGE_TRANSPORT: u32 = 4;
+
+const TAG_SIZE: usize = CHACHA20_POLY1305_TAG_SIZE;
+const MAC_SIZE: usize = 16;
+const TIMESTAMP_SIZE: usize = 12;
+
+/// The packets are padded to a multiple of this size before they are encrypted.
+const PADDING_SIZE: usize = 16;
+
+const REKEY_AFTER_MESSAGES: u64 = 1 << 60;
+const REJECT_AFTER_MESSAGES: u64 = u64::MAX - (1 << 13);
+const REKEY_AFTER_TIME: Duration = Duration::from_secs(120);
+const REJECT_AFTER_TIME: Duration = Duration::from_secs(180);
+
+/// The static key pair of a WireGuard iface.
+pub(super) struct StaticIdentity {
+    private_key: Key,
+    public_key: Key,
+    /// The key to compute `mac1` of the handshake messages sent to the iface.
+    mac1_key: Key,
+}
+
+impl StaticIdentity {
+    /// Creates the identity from the private key.
+    ///
+    /// Like Linux, the private key is clamped as required by X25519.
+    pub(super) fn new(mut private_key: Key) -> Self {
+        private_key[0] &= 248;
+        private_key[31] &= 127;
+        private_key[31] |= 64;
+
+        let public_key = x25519_public_key(&private_key);
+        Self {
+            private_key,
+            public_key,
+            mac1_key: mac1_key(&public_key),
+        }
+    }
+
+    pub(super) fn private_key(&self) -> &Key {
+        &self.private_key
+    }
+
+    pub(super) fn public_key(&self) -> &Key {
+        &self.public_key
+    }
+}
+
+/// A handshake initiation message.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct InitiationMessage {
+    type_: u32,
+    sender: u32,
+    ephemeral: Key,
+    static_: [u8; X25519_KEY_SIZE + TAG_SIZE],
+    timestamp: [u8; TIMESTAMP_SIZE + TAG_SIZE],
+    mac1: [u8; MAC_SIZE],
+    mac2: [u8; MAC_SIZE],
+}
+
+/// A handshake response message.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct ResponseMessage {
+    type_: u32,
+    sender: u32,
+    receiver: u32,
+    ephemeral: Key,
+    empty: [u8; TAG_SIZE],
+    mac1: [u8; MAC_SIZE],
+    mac2: [u8; MAC_SIZE],
+}
+
+impl ResponseMessage {
+    /// Returns the index of the handshake that the message responds to.
+    pub(super) fn receiver(&self) -> u32 {
+        u32::from_le(self.receiver)
+    }
+}
+
+/// The header of a transport data message.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct TransportHeader {
+    type_: u32,
+    receiver: u32,
+    counter: u64,
+}
+
+/// A transport data message, which carries an encrypted packet.
+pub(super) struct TransportMessage<'a> {
+    receiver: u32,
+    counter: u64,
+    /// The encrypted packet followed by the authentication tag.
+    payload: &'a mut [u8],
+}
+
+impl TransportMessage<'_> {
+    /// Returns the index of the keypair that protects the message.
+    pub(super) fn receiver(&self) -> u32 {
+        self.receiver
+    }
+}
+
+/// A message received from a peer.
+pub(super) enum Message<'a> {
+    Initiation(InitiationMessage),
+    Response(ResponseMessage),
+    Transport(TransportMessage<'a>),
+}
+
+/// Parses the message in the UDP datagram.
+///
+/// This function returns `None` if the datagram is not a valid message.
+pub(super) fn parse_message(datagram: &mut [u8]) -> Option<Message<'_>> {
+    let type_ = u32::from_le_bytes(datagram.get(..4)?.try_into().unwrap());
+
+    let message = match type_ {
+        MESSAGE_INITIATION if datagram.len() == size_of::<InitiationMessage>() => {
+            Message::Initiation(InitiationMessage::from_bytes(datagram))
+        }
+        MESSAGE_RESPONSE if datagram.len() == size_of::<ResponseMessage>() => {
+            Message::Response(ResponseMessage::from_bytes(datagram))
+        }
+        MESSAGE_TRANSPORT if datagram.len() >= size_of::<TransportHeader>() + TAG_SIZE => {
+            let header = TransportHeader::from_first_bytes(datagram);
+            Message::Transport(TransportMessage {
+                receiver: u32::from_le(header.receiver),
+                counter: u64::from_le(header.counter),
+                payload: &mut datagram[size_of::<TransportHeader>()..],
+            })
+        }
+        // TODO: Support cookie replies, which are sent by peers under load.
+        _ => return None,
+    };
+
+    Some(message)
+}
+
+/// A handshake initiated by the iface, which is waiting for the response.
+pub(super) struct InitiatorHandshake {
+    local_index: u32,
+    ephemeral_private_key: Key,
+    state: SymmetricState,
+}
+
+impl InitiatorHandshake {
+    pub(super) fn local_index(&self) -> u32 {
+        self.local_index
+    }
+}
+
+/// Creates a handshake initiation message to the peer.
+pub(super) fn create_initiation(
+    identity: &StaticIdentity,
+    peer_public_key: &Key,
+    local_index: u32,
+) -> Option<(InitiationMessage, InitiatorHandshake)> {
+    let mut state = SymmetricState::new(peer_public_key);
+
+    let mut message = InitiationMessage::new_zeroed();
+    message.type_ = MESSAGE_INITIATION.to_le();
+    message.sender = local_index.to_le();
+
+    let ephemeral_private_key = random_key();
+    message.ephemeral = x25519_public_key(&ephemeral_private_key);
+    state.mix_hash(&message.ephemeral);
+    state.mix_key(&message.ephemeral);
+
+    let key = state.mix_key_and_get(&dh(&ephemeral_private_key, peer_public_key)?);
+    state.encrypt_and_hash(&key, &identity.public_key, &mut message.static_);
+
+    let key = state.mix_key_and_get(&dh(&identity.private_key, peer_public_key)?);
+    state.encrypt_and_hash(&key, &tai64n_now(), &mut message.timestamp);
+
+    let mac1_offset = offset_of!(InitiationMessage, mac1);
+    message.mac1 = mac(&mac1_key(peer_public_key), &message.as_bytes()[..mac1_offset]);
+
+    let handshake = InitiatorHandshake {
+        local_index,
+        ephemeral_private_key,
+        state,
+    };
+    Some((message, handshake))
+}
+
+/// A handshake initiation that has been authenticated, which the iface can respond to.
+pub(super) struct ConsumedInitiation {
+    remote_index: u32,
+    peer_public_key: Key,
+    timestamp: [u8; TIMESTAMP_SIZE],
+    remote_ephemeral: Key,
+    state: SymmetricState,
+}
+
+impl ConsumedInitiation {
+    /// Returns the static public key of the initiator.
+    pub(super) fn peer_public_key(&self) -> &Key {
+        &self.peer_public_key
+    }
+
+    /// Returns the TAI64N timestamp in the initiation, which prevents replay attacks.
+    pub(super) fn timestamp(&self) -> &[u8; TIMESTAMP_SIZE] {
+        &self.timestamp
+    }
+}
+
+/// Consumes a handshake initiation message sent to the iface.
+///
+/// This function returns `None` if the message cannot be authenticated.
+pub(super) fn consume_initiation(
+    identity: &StaticIdentity,
+    message: &InitiationMessage,
+) -> Option<ConsumedInitiation> {
+    let mac1_offset = offset_of!(InitiationMessage, mac1);
+    let mac1 = mac(&identity.mac1_key, &message.as_bytes()[..mac1_offset]);
+    if !ct_eq(&mac1, &message.mac1) {
+        return None;
+    }
+
+    let mut state = SymmetricState::new(&identity.public_key);
+    state.mix_hash(&message.ephemeral);
+    state.mix_key(&message.ephemeral);
+
+    let key = state.mix_key_and_get(&dh(&identity.private_key, &message.ephemeral)?);
+    let mut peer_public_key = [0u8; X25519_KEY_SIZE];
+    state.decrypt_and_hash(&key, &message.static_, &mut peer_public_key)?;
+
+    let key = state.mix_key_and_get(&dh(&identity.private_key, &peer_public_key)?);
+    let mut timestamp = [0u8; TIMESTAMP_SIZE];
+    state.decrypt_and_hash(&key, &message.timestamp, &mut timestamp)?;
+
+    Some(ConsumedInitiation {
+        remote_index: u32::from_le(message.sender),
+        peer_public_key,
+        timestamp,
+        remote_ephemeral: message.ephemeral,
+        state,
+    })
+}
+
+/// Creates a handshake response message to the initiation.
+///
+/// The returned keypair can be used to send data only after the initiator confirms it by sending
+/// the first transport data message.
+pub(super) fn create_response(
+    initiation: ConsumedInitiation,
+    preshared_key: &Key,
+    local_index: u32,
+    now: Duration,
+) -> Option<(ResponseMessage, Keypair)> {
+    let ConsumedInitiation {
+        remote_index,
+        peer_public_key,
+        remote_ephemeral,
+        mut state,
+        ..
+    } = initiation;
+
+    let mut message = ResponseMessage::new_zeroed();
+    message.type_ = MESSAGE_RESPONSE.to_le();
+    message.sender = local_index.to_le();
+    message.receiver = remote_index.to_le();
+
+    let ephemeral_private_key = random_key();
+    message.ephemeral = x25519_public_key(&ephemeral_private_key);
+    state.mix_hash(&message.ephemeral);
+    state.mix_key(&message.ephemeral);
+    state.mix_key(&dh(&ephemeral_private_key, &remote_ephemeral)?);
+    state.mix_key(&dh(&ephemeral_private_key, &peer_public_key)?);
+
+    let key = state.mix_psk(preshared_key);
+    state.encrypt_and_hash(&key, &[], &mut message.empty);
+
+    let mac1_offset = offset_of!(ResponseMessage, mac1);
+    message.mac1 = mac(&mac1_key(&peer_public_key), &message.as_bytes()[..mac1_offset]);
+
+    let (initiator_key, responder_key) = state.split();
+    let keypair = Keypair::new(
+        &responder_key,
+        &initiator_key,
+        local_index,
+        remote_index,
+        false,
+        now,
+    );
+    Some((message, keypair))
+}
+
+/// Consumes a handshake response message to the handshake initiated by the iface.
+///
+/// This function returns `None` if the message cannot be authenticated.
+pub(super) fn consume_response(
+    identity: &StaticIdentity,
+    handshake: &InitiatorHandshake,
+    preshared_key: &Key,
+    message: &ResponseMessage,
+    now: Duration,
+) -> Option<Keypair> {
+    let mac1_offset = offset_of!(ResponseMessage, mac1);
+    let mac1 = mac(&identity.mac1_key, &message.as_bytes()[..mac1_offset]);
+    if !ct_eq(&mac1, &message.mac1) || message.receiver() != handshake.local_index {
+        return None;
+    }
+
+    let mut state = handshake.state.clone();
+    state.mix_hash(&message.ephemeral);
+    state.mix_key(&message.ephemeral);
+    state.mix_key(&dh(&handshake.ephemeral_private_key, &message.ephemeral)?);
+    state.mix_key(&dh(&identity.private_key, &message.ephemeral)?);
+
+    let key = state.mix_psk(preshared_key);
+    state.decrypt_and_hash(&key, &message.empty, &mut [])?;
+
+    let (initiator_key, responder_key) = state.split();
+    let keypair = Keypair::new(
+        &initiator_key,
+        &responder_key,
+        handshake.local_index,
+        u32::from_le(message.sender),
+        true,
+        now,
+    );
+    Some(keypair)
+}
+
+/// The keys of a session, which protect the transport data in both directions.
+pub(super) struct Keypair {
+    sending_key: Box<dyn Aead>,
+    receiving_key: Box<dyn Aead>,
+    local_index: u32,
+    remote_index: u32,
+    is_initiator: bool,
+    created_at: Duration,
+    send_counter: u64,
+    replay_window: ReplayWindow,
+}
+
+impl Keypair {
+    fn new(
+        sending_key: &Key,
+        receiving_key: &Key,
+        local_index: u32,
+        remote_index: u32,
+        is_initiator: bool,
+        now: Duration,
+    ) -> Self {
+        Self {
+            sending_key: aead(sending_key),
+            receiving_key: aead(receiving_key),
+            local_index,
+            remote_index,
+            is_initiator,
+            created_at: now,
+            send_counter: 0,
+            replay_window: ReplayWindow::new(),
+        }
+    }
+
+    pub(super) fn local_index(&self) -> u32 {
+        self.local_index
+    }
+
+    /// Returns whether the keypair can no longer be used to send or receive data.
+    pub(super) fn is_expired(&self, now: Duration) -> bool {
+        now >= self.created_at + REJECT_AFTER_TIME || self.send_counter >= REJECT_AFTER_MESSAGES
+    }
+
+    /// Returns whether a new handshake should be initiated to replace the keypair.
+    pub(super) fn needs_rekey(&self, now: Duration) -> bool {
+        self.send_counter >= REKEY_AFTER_MESSAGES
+            || (self.is_initiator && now >= self.created_at + REKEY_AFTER_TIME)
+    }
+
+    /// Encrypts the packet and returns the transport data message.
+    ///
+    /// An empty packet is a keepalive message.
+    pub(super) fn encrypt(&mut self, packet: &[u8]) -> Vec<u8> {
+        let counter = self.send_counter;
+        self.send_counter += 1;
+
+        let header = TransportHeader {
+            type_: MESSAGE_TRANSPORT.to_le(),
+            receiver: self.remote_index.to_le(),
+            counter: counter.to_le(),
+        };
+        let padded_len = packet.len().align_up(PADDING_SIZE);
+        let mut datagram = vec![0u8; size_of::<TransportHeader>() + padded_len + TAG_SIZE];
+
+        let (header_bytes, payload) = datagram.split_at_mut(size_of::<TransportHeader>());
+        header_bytes.copy_from_slice(header.as_bytes());
+        let (data, tag) = payload.split_at_mut(padded_len);
+        data[..packet.len()].copy_from_slice(packet);
+        self.sending_key.seal(&nonce(counter), &[], data, tag);
+
+        datagram
+    }
+
+    /// Decrypts the transport data message and returns the (padded) packet.
+    ///
+    /// This method returns `None` if the message cannot be authenticated or is a replay.
+    pub(super) fn decrypt(&mut self, message: TransportMessage) -> Option<Vec<u8>> {
+        let TransportMessage {
+            counter, payload, ..
+        } = message;
+        if counter >= REJECT_AFTER_MESSAGES {
+            return None;
+        }
+
+        let (data, tag) = payload.split_at_mut(payload.len() - TAG_SIZE);
+        self.receiving_key
+            .open(&nonce(counter), &[], data, tag)
+            .ok()?;
+        // The counter is recorded only after the message is authenticated.
+        if !self.replay_window.check_and_update(counter) {
+            return None;
+        }
+
+        Some(data.to_vec())
+    }
+}
+
+/// A sliding window of the received counters, which rejects the replayed messages.
+///
+/// The window covers the latest 64 counters, so messages reordered by more than that are
+/// dropped as well.
+struct ReplayWindow {
+    /// The greatest counter received so far, plus one.
+    next: u64,
+    /// The bitmap of the received counters, where bit `i` represents the counter `next - 1 - i`.
+    bitmap: u64,
+}
+
+impl ReplayWindow {
+    const fn new() -> Self {
+        Self { next: 0, bitmap: 0 }
+    }
+
+    /// Records the counter and returns whether it has not been received before.
+    fn check_and_update(&mut self, counter: u64) -> bool {
+        if counter >= self.next {
+            let shift = counter - self.next + 1;
+            self.bitmap = if shift >= u64::BITS as u64 {
+                0
+            } else {
+                self.bitmap << shift
+            };
+            self.bitmap |= 1;
+            self.next = counter + 1;
+            return true;
+        }
+
+        let offset = self.next - 1 - counter;
+        if offset >= u64::BITS as u64 {
+            return false;
+        }
+        let mask = 1 << offset;
+        if self.bitmap & mask != 0 {
+            return false;
+        }
+        self.bitmap |= mask;
+        true
+    }
+}
+
+/// The chaining key and the hash of a handshake.
+#[derive(Clone)]
+struct SymmetricState {
+    chaining_key: Key,
+    hash: Key,
+}
+
+impl SymmetricState {
+    /// Creates the initial state of a handshake to the responder.
+    fn new(responder_public_key: &Key) -> Self {
+        let chaining_key = blake2s(&[CONSTRUCTION]);
+        let hash = blake2s(&[&chaining_key, IDENTIFIER]);
+        let hash = blake2s(&[&hash, responder_public_key]);
+
+        Self { chaining_key, hash }
+    }
+
+    fn mix_hash(&mut self, data: &[u8]) {
+        self.hash = blake2s(&[&self.hash, data]);
+    }
+
+    fn mix_key(&mut self, input: &[u8]) {
+        let [chaining_key] = kdf(&self.chaining_key, input);
+        self.chaining_key = chaining_key;
+    }
+
+    /// Mixes the input into the chaining key and returns a key for encryption.
+    fn mix_key_and_get(&mut self, input: &[u8]) -> Key {
+        let [chaining_key, key] = kdf(&self.chaining_key, input);
+        self.chaining_key = chaining_key;
+        key
+    }
+
+    /// Mixes the pre-shared key into both the chaining key and the hash, and returns a key for
+    /// encryption.
+    fn mix_psk(&mut self, preshared_key: &Key) -> Key {
+        let [chaining_key, temp, key] = kdf(&self.chaining_key, preshared_key);
+        self.chaining_key = chaining_key;
+        self.mix_hash(&temp);
+        key
+    }
+
+    /// Encrypts the plaintext to `out` and mixes the ciphertext into the hash.
+    ///
+    /// The length of `out` must be the length of the plaintext plus [`TAG_SIZE`].
+    fn encrypt_and_hash(&mut self, key: &Key, plaintext: &[u8], out: &mut [u8]) {
+        let (data, tag) = out.split_at_mut(plaintext.len());
+        data.copy_from_slice(plaintext);
+        aead(key).seal(&nonce(0), &self.hash, data, tag);
+
+        self.mix_hash(out);
+    }
+
+    /// Decrypts the ciphertext to `out` and mixes the ciphertext into the hash.
+    ///
+    /// The length of `out` must be the length of the ciphertext minus [`TAG_SIZE`].
+    fn decrypt_and_hash(&mut self, key: &Key, ciphertext: &[u8], out: &mut [u8]) -> Option<()> {
+        let (data, tag) = ciphertext.split_at(out.len());
+        out.copy_from_slice(data);
+        aead(key).open(&nonce(0), &self.hash, out, tag).ok()?;
+
+        self.mix_hash(ciphertext);
+        Some(())
+    }
+
+    /// Derives the keys of the transport data sent by the initiator and the responder.
+    fn split(&self) -> (Key, Key) {
+        let [initiator_key, responder_key] = kdf(&self.chaining_key, &[]);
+        (initiator_key, responder_key)
+    }
+}
+
+/// Derives `N` keys from the chaining key and the input with HKDF.
+fn kdf<const N: usize>(chaining_key: &Key, input: &[u8]) -> [Key; N] {
+    let secret = hmac_blake2s(chaining_key, &[input]);
+
+    let mut outputs = [[0u8; X25519_KEY_SIZE]; N];
+    for i in 0..N {
+        let prev: &[u8] = if i == 0 { &[] } else { &outputs[i - 1] };
+        outputs[i] = hmac_blake2s(&secret, &[prev, &[i as u8 + 1]]);
+    }
+    outputs
+}
+
+/// Computes the Diffie-Hellman shared secret.
+///
+/// This function returns `None` if the public key is a low-order point, which results in an
+/// all-zero shared secret.
+fn dh(private_key: &Key, public_key: &Key) -> Option<Key> {
+    let shared = x25519(private_key, public_key);
+    if ct_eq(&shared, &[0u8; X25519_KEY_SIZE]) {
+        return None;
+    }
+    Some(shared)
+}
+
+fn mac1_key(public_key: &Key) -> Key {
+    blake2s(&[LABEL_MAC1, public_key])
+}
+
+fn mac(key: &Key, data: &[u8]) -> [u8; MAC_SIZE] {
+    let mut hasher = Blake2s::new_keyed(key, MAC_SIZE);
+    hasher.update(data);
+
+    let mut mac = [0u8; MAC_SIZE];
+    hasher.finalize_into(&mut mac);
+    mac
+}
+
+/// Creates the AEAD cipher (i.e., ChaCha20-Poly1305) with the key.
+fn aead(key: &Key) -> Box<dyn Aead> {
+    // The key size is valid for ChaCha20-Poly1305, so the creation never fails.
+    AeadAlg::Rfc7539ChaCha20Poly1305.new_cipher(key).unwrap()
+}
+
+fn nonce(counter: u64) -> [u8; CHACHA20_POLY1305_NONCE_SIZE] {
+    let mut nonce = [0u8; CHACHA20_POLY1305_NONCE_SIZE];
+    nonce[4..].copy_from_slice(&counter.to_le_bytes());
+    nonce
+}
+
+fn random_key() -> Key {
+    let mut key = [0u8; X25519_KEY_SIZE];
+    getrandom(&mut key);
+    key
+}
+
+/// Returns the current time in the TAI64N format, which can be compared as bytes.
+fn tai64n_now() -> [u8; TIMESTAMP_SIZE] {
+    /// The TAI64 label of the Unix epoch, including the 10-second offset between TAI and UTC.
+    const TAI64_EPOCH: u64 = 0x4000_0000_0000_000a;
+
+    let now = RealTimeCoarseClock::get().read_time();
+
+    let mut timestamp = [0u8; TIMESTAMP_SIZE];
+    timestamp[..8].copy_from_slice(&(TAI64_EPOCH + now.as_secs()).to_be_bytes());
+    timestamp[8..].copy_from_slice(&now.subsec_nanos().to_be_bytes());
+    timestamp
+}
+
+/// Compares the bytes in constant time.
+fn ct_eq(lhs: &[u8], rhs: &[u8]) -> bool {
+    lhs.len() == rhs.len()
+        && lhs
+            .iter()
+            .zip(rhs)
+            .fold(0u8, |diff, (lhs, rhs)| diff | (lhs ^ rhs))
+            == 0
+}
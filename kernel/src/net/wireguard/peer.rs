@@ -0,0 +1,316 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::collections::VecDeque;
+use core::{net::SocketAddrV4, time::Duration};
+
+use super::noise::{
+    self, ConsumedInitiation, InitiatorHandshake, Key, Keypair, ResponseMessage, StaticIdentity,
+    TransportMessage,
+};
+use crate::{prelude::*, time::clocks::RealTimeCoarseClock};
+
+/// A peer of a WireGuard iface.
+pub(super) struct Peer {
+    public_key: Key,
+    preshared_key: Key,
+    /// The address that the datagrams to the peer are sent to.
+    ///
+    /// The endpoint is updated to the source address of the latest authenticated datagram from
+    /// the peer, so the peer can roam.
+    endpoint: Option<SocketAddrV4>,
+    persistent_keepalive_interval: u16,
+    /// The handshake initiated by the iface, which is waiting for the response.
+    handshake: Option<InitiatorHandshake>,
+    /// The time (in the monotonic clock) when the last handshake initiation was sent.
+    last_initiation_sent: Option<Duration>,
+    /// The keypair that is used to send data.
+    current: Option<Keypair>,
+    /// The keypair replaced by the current keypair, which may still be used by the peer.
+    previous: Option<Keypair>,
+    /// The keypair created by responding to a handshake initiation.
+    ///
+    /// The keypair becomes the current keypair once the peer sends data with it.
+    next: Option<Keypair>,
+    /// The packets that wait for a keypair.
+    staged_packets: VecDeque<Vec<u8>>,
+    /// The timestamp of the latest handshake initiation from the peer.
+    latest_timestamp: Option<[u8; 12]>,
+    /// The time (in the real-time clock) when the last handshake completed.
+    last_handshake_time: Option<Duration>,
+    rx_bytes: u64,
+    tx_bytes: u64,
+}
+
+/// The minimum interval between two handshake initiations to the same peer.
+const REKEY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The maximum number of packets that wait for a keypair.
+///
+/// This is the value of `MAX_STAGED_PACKETS` in Linux. If there are more packets, the oldest ones
+/// are dropped.
+const MAX_STAGED_PACKETS: usize = 128;
+
+impl Peer {
+    pub(super) fn new(public_key: Key) -> Self {
+        Self {
+            public_key,
+            preshared_key: [0; 32],
+            endpoint: None,
+            persistent_keepalive_interval: 0,
+            handshake: None,
+            last_initiation_sent: None,
+            current: None,
+            previous: None,
+            next: None,
+            staged_packets: VecDeque::new(),
+            latest_timestamp: None,
+            last_handshake_time: None,
+            rx_bytes: 0,
+            tx_bytes: 0,
+        }
+    }
+
+    pub(super) fn public_key(&self) -> &Key {
+        &self.public_key
+    }
+
+    pub(super) fn preshared_key(&self) -> &Key {
+        &self.preshared_key
+    }
+
+    pub(super) fn set_preshared_key(&mut self, preshared_key: Key) {
+        self.preshared_key = preshared_key;
+    }
+
+    pub(super) fn endpoint(&self) -> Option<SocketAddrV4> {
+        self.endpoint
+    }
+
+    pub(super) fn set_endpoint(&mut self, endpoint: SocketAddrV4) {
+        self.endpoint = Some(endpoint);
+    }
+
+    pub(super) fn persistent_keepalive_interval(&self) -> u16 {
+        self.persistent_keepalive_interval
+    }
+
+    pub(super) fn set_persistent_keepalive_interval(&mut self, interval: u16) {
+        self.persistent_keepalive_interval = interval;
+    }
+
+    pub(super) fn last_handshake_time(&self) -> Option<Duration> {
+        self.last_handshake_time
+    }
+
+    pub(super) fn rx_bytes(&self) -> u64 {
+        self.rx_bytes
+    }
+
+    pub(super) fn tx_bytes(&self) -> u64 {
+        self.tx_bytes
+    }
+
+    /// Returns whether the local index belongs to the handshake or a keypair of the peer.
+    pub(super) fn owns_index(&self, index: u32) -> bool {
+        self.handshake
+            .as_ref()
+            .is_some_and(|handshake| handshake.local_index() == index)
+            || [&self.current, &self.previous, &self.next]
+                .into_iter()
+                .flatten()
+                .any(|keypair| keypair.local_index() == index)
+    }
+
+    pub(super) fn has_staged_packets(&self) -> bool {
+        !self.staged_packets.is_empty()
+    }
+
+    /// Queues a packet to send to the peer.
+    pub(super) fn stage_packet(&mut self, packet: Vec<u8>) {
+        if self.staged_packets.len() >= MAX_STAGED_PACKETS {
+            self.staged_packets.pop_front();
+        }
+        self.staged_packets.push_back(packet);
+    }
+
+    /// Encrypts the staged packets, initiating a handshake if there is no usable keypair.
+    ///
+    /// The local index is used if a handshake is initiated. This method returns the datagrams to
+    /// send to the endpoint of the peer.
+    pub(super) fn flush(
+        &mut self,
+        identity: &StaticIdentity,
+        local_index: u32,
+        now: Duration,
+    ) -> Vec<Vec<u8>> {
+        if self.endpoint.is_none() {
+            // Like Linux, the packets are dropped if the endpoint of the peer is unknown.
+            self.staged_packets.clear();
+            return Vec::new();
+        }
+
+        let mut datagrams = Vec::new();
+
+        let keypair = self
+            .current
+            .as_ref()
+            .filter(|keypair| !keypair.is_expired(now));
+        if let Some(keypair) = keypair {
+            let needs_rekey = keypair.needs_rekey(now);
+            datagrams = self.encrypt_staged();
+            if !needs_rekey {
+                return datagrams;
+            }
+        }
+
+        // TODO: Retry the handshake with timers instead of waiting for the next packet.
+        if self
+            .last_initiation_sent
+            .is_some_and(|sent| now < sent + REKEY_TIMEOUT)
+        {
+            return datagrams;
+        }
+        let Some((message, handshake)) =
+            noise::create_initiation(identity, &self.public_key, local_index)
+        else {
+            return datagrams;
+        };
+        self.handshake = Some(handshake);
+        self.last_initiation_sent = Some(now);
+
+        let datagram = message.as_bytes().to_vec();
+        self.tx_bytes += datagram.len() as u64;
+        datagrams.push(datagram);
+
+        datagrams
+    }
+
+    /// Responds to a handshake initiation from the peer.
+    ///
+    /// This method returns the datagram to send to the endpoint of the peer, or `None` if the
+    /// initiation is a replay.
+    pub(super) fn respond(
+        &mut self,
+        initiation: ConsumedInitiation,
+        local_index: u32,
+        endpoint: SocketAddrV4,
+        now: Duration,
+    ) -> Option<Vec<u8>> {
+        let timestamp = *initiation.timestamp();
+        if self
+            .latest_timestamp
+            .is_some_and(|latest| timestamp <= latest)
+        {
+            return None;
+        }
+
+        let (message, keypair) =
+            noise::create_response(initiation, &self.preshared_key, local_index, now)?;
+        self.latest_timestamp = Some(timestamp);
+        self.next = Some(keypair);
+        self.receive(endpoint, size_of::<noise::InitiationMessage>());
+
+        let datagram = message.as_bytes().to_vec();
+        self.tx_bytes += datagram.len() as u64;
+        Some(datagram)
+    }
+
+    /// Completes the handshake initiated by the iface with the response from the peer.
+    ///
+    /// This method returns the datagrams to send to the endpoint of the peer, or `None` if the
+    /// response cannot be authenticated.
+    pub(super) fn complete_handshake(
+        &mut self,
+        identity: &StaticIdentity,
+        message: &ResponseMessage,
+        endpoint: SocketAddrV4,
+        now: Duration,
+    ) -> Option<Vec<Vec<u8>>> {
+        let handshake = self.handshake.as_ref()?;
+        let keypair =
+            noise::consume_response(identity, handshake, &self.preshared_key, message, now)?;
+
+        self.handshake = None;
+        self.previous = self.current.replace(keypair);
+        self.next = None;
+        self.last_handshake_time = Some(RealTimeCoarseClock::get().read_time());
+        self.receive(endpoint, size_of::<ResponseMessage>());
+
+        let mut datagrams = self.encrypt_staged();
+        if datagrams.is_empty() {
+            // The responder cannot use the keypair until it receives data, so a keepalive message
+            // is sent to confirm the keypair.
+            let datagram = self.current.as_mut().unwrap().encrypt(&[]);
+            self.tx_bytes += datagram.len() as u64;
+            datagrams.push(datagram);
+        }
+        Some(datagrams)
+    }
+
+    /// Decrypts a transport data message from the peer.
+    ///
+    /// This method returns the decrypted (padded) packet, which is empty for keepalive messages,
+    /// or `None` if the message cannot be authenticated.
+    pub(super) fn decrypt(
+        &mut self,
+        message: TransportMessage,
+        endpoint: SocketAddrV4,
+        len: usize,
+        now: Duration,
+    ) -> Option<Vec<u8>> {
+        let index = message.receiver();
+        let owns = |keypair: &Option<Keypair>| {
+            keypair
+                .as_ref()
+                .is_some_and(|keypair| keypair.local_index() == index)
+        };
+
+        let is_next = owns(&self.next);
+        let keypair = if owns(&self.current) {
+            &mut self.current
+        } else if owns(&self.previous) {
+            &mut self.previous
+        } else if is_next {
+            &mut self.next
+        } else {
+            return None;
+        };
+        let keypair = keypair.as_mut().unwrap();
+        if keypair.is_expired(now) {
+            return None;
+        }
+        let packet = keypair.decrypt(message)?;
+
+        if is_next {
+            // The peer has confirmed the keypair created by the response.
+            self.previous = self.current.take();
+            self.current = self.next.take();
+            self.last_handshake_time = Some(RealTimeCoarseClock::get().read_time());
+        }
+        self.receive(endpoint, len);
+
+        Some(packet)
+    }
+
+    fn receive(&mut self, endpoint: SocketAddrV4, len: usize) {
+        self.endpoint = Some(endpoint);
+        self.rx_bytes += len as u64;
+    }
+
+    /// Encrypts the staged packets with the current keypair.
+    fn encrypt_staged(&mut self) -> Vec<Vec<u8>> {
+        let keypair = self.current.as_mut().unwrap();
+
+        let datagrams = self
+            .staged_packets
+            .drain(..)
+            .map(|packet| keypair.encrypt(&packet))
+            .collect::<Vec<_>>();
+        self.tx_bytes += datagrams
+            .iter()
+            .map(|datagram| datagram.len() as u64)
+            .sum::<u64>();
+
+        datagrams
+    }
+}
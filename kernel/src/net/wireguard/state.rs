@@ -0,0 +1,262 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::net::SocketAddrV4;
+
+use aster_bigtcp::wire::Ipv4Address;
+
+use super::{
+    DeviceConfig, DeviceUpdate, PeerConfig,
+    allowed_ips::AllowedIps,
+    noise::{self, Key, Message, StaticIdentity},
+    peer::Peer,
+};
+use crate::{prelude::*, time::clocks::MonotonicCoarseClock, util::random::getrandom};
+
+/// The protocol state of a WireGuard iface.
+pub(super) struct DeviceState {
+    identity: Option<StaticIdentity>,
+    fwmark: u32,
+    peers: Vec<Peer>,
+    allowed_ips: AllowedIps,
+    /// The next index to identify a handshake or a keypair of the iface.
+    next_index: u32,
+}
+
+impl DeviceState {
+    pub(super) fn new() -> Self {
+        let mut next_index = [0u8; 4];
+        getrandom(&mut next_index);
+
+        Self {
+            identity: None,
+            fwmark: 0,
+            peers: Vec::new(),
+            allowed_ips: AllowedIps::new(),
+            next_index: u32::from_ne_bytes(next_index),
+        }
+    }
+
+    /// Returns the configuration of the iface, except for the listen port.
+    pub(super) fn config(&self) -> DeviceConfig {
+        let peers = self
+            .peers
+            .iter()
+            .map(|peer| PeerConfig {
+                public_key: *peer.public_key(),
+                preshared_key: *peer.preshared_key(),
+                endpoint: peer.endpoint(),
+                persistent_keepalive_interval: peer.persistent_keepalive_interval(),
+                last_handshake_time: peer.last_handshake_time().unwrap_or_default(),
+                rx_bytes: peer.rx_bytes(),
+                tx_bytes: peer.tx_bytes(),
+                allowed_ips: self.allowed_ips.peer_cidrs(peer.public_key()),
+            })
+            .collect();
+
+        DeviceConfig {
+            private_key: self.identity.as_ref().map(|identity| *identity.private_key()),
+            public_key: self.identity.as_ref().map(|identity| *identity.public_key()),
+            listen_port: 0,
+            fwmark: self.fwmark,
+            peers,
+        }
+    }
+
+    /// Applies the changes to the configuration of the iface, except for the listen port.
+    pub(super) fn update(&mut self, update: DeviceUpdate) {
+        if let Some(private_key) = update.private_key {
+            let identity = StaticIdentity::new(private_key);
+            // Like Linux, the peer with the same public key as the iface is removed.
+            self.remove_peer(identity.public_key());
+            self.identity = Some(identity);
+        }
+        if let Some(fwmark) = update.fwmark {
+            self.fwmark = fwmark;
+        }
+        if update.replace_peers {
+            self.peers.clear();
+            self.allowed_ips = AllowedIps::new();
+        }
+
+        for peer_update in update.peers {
+            let public_key = peer_update.public_key;
+            if peer_update.remove {
+                self.remove_peer(&public_key);
+                continue;
+            }
+            if self
+                .identity
+                .as_ref()
+                .is_some_and(|identity| *identity.public_key() == public_key)
+            {
+                continue;
+            }
+
+            let peer = match self
+                .peers
+                .iter()
+                .position(|peer| *peer.public_key() == public_key)
+            {
+                Some(pos) => &mut self.peers[pos],
+                None if peer_update.update_only => continue,
+                None => {
+                    self.peers.push(Peer::new(public_key));
+                    self.peers.last_mut().unwrap()
+                }
+            };
+
+            if let Some(preshared_key) = peer_update.preshared_key {
+                peer.set_preshared_key(preshared_key);
+            }
+            if let Some(endpoint) = peer_update.endpoint {
+                peer.set_endpoint(endpoint);
+            }
+            if let Some(interval) = peer_update.persistent_keepalive_interval {
+                // TODO: Send keepalive messages when the interval is not zero.
+                peer.set_persistent_keepalive_interval(interval);
+            }
+
+            if peer_update.replace_allowed_ips {
+                self.allowed_ips.remove_peer(&public_key);
+            }
+            for cidr in peer_update.allowed_ips {
+                self.allowed_ips.insert(cidr, &public_key);
+            }
+        }
+    }
+
+    /// Sends a packet from the iface to the peer selected by its destination address.
+    ///
+    /// This method returns the endpoint of the peer and the datagrams to send to it.
+    pub(super) fn send_packet(&mut self, packet: Vec<u8>) -> Option<(SocketAddrV4, Vec<Vec<u8>>)> {
+        // TODO: Support IPv6 packets.
+        if packet.len() < IPV4_HEADER_LEN || packet[0] >> 4 != 4 {
+            return None;
+        }
+        let dst_addr = Ipv4Address::from(<[u8; 4]>::try_from(&packet[16..20]).unwrap());
+        let peer_key = *self.allowed_ips.lookup(&dst_addr)?;
+
+        let local_index = self.alloc_index();
+        let identity = self.identity.as_ref()?;
+        let peer = self
+            .peers
+            .iter_mut()
+            .find(|peer| *peer.public_key() == peer_key)?;
+
+        let now = MonotonicCoarseClock::get().read_time();
+        peer.stage_packet(packet);
+        let datagrams = peer.flush(identity, local_index, now);
+
+        Some((peer.endpoint()?, datagrams))
+    }
+
+    /// Handles a datagram received from the source address.
+    ///
+    /// This method returns the datagrams to send back to the source address, and the packet to be
+    /// received by the iface.
+    pub(super) fn handle_datagram(
+        &mut self,
+        datagram: &mut [u8],
+        src: SocketAddrV4,
+    ) -> (Vec<Vec<u8>>, Option<Vec<u8>>) {
+        let len = datagram.len();
+        let now = MonotonicCoarseClock::get().read_time();
+        let local_index = self.alloc_index();
+        let Some(identity) = self.identity.as_ref() else {
+            return (Vec::new(), None);
+        };
+
+        match noise::parse_message(datagram) {
+            Some(Message::Initiation(message)) => {
+                let Some(initiation) = noise::consume_initiation(identity, &message) else {
+                    return (Vec::new(), None);
+                };
+                let Some(peer) = self
+                    .peers
+                    .iter_mut()
+                    .find(|peer| peer.public_key() == initiation.peer_public_key())
+                else {
+                    return (Vec::new(), None);
+                };
+
+                let reply = peer.respond(initiation, local_index, src, now);
+                (reply.into_iter().collect(), None)
+            }
+            Some(Message::Response(message)) => {
+                let Some(peer) = self
+                    .peers
+                    .iter_mut()
+                    .find(|peer| peer.owns_index(message.receiver()))
+                else {
+                    return (Vec::new(), None);
+                };
+
+                let replies = peer.complete_handshake(identity, &message, src, now);
+                (replies.unwrap_or_default(), None)
+            }
+            Some(Message::Transport(message)) => {
+                let Some(peer) = self
+                    .peers
+                    .iter_mut()
+                    .find(|peer| peer.owns_index(message.receiver()))
+                else {
+                    return (Vec::new(), None);
+                };
+
+                let Some(packet) = peer.decrypt(message, src, len, now) else {
+                    return (Vec::new(), None);
+                };
+                let replies = if peer.has_staged_packets() {
+                    peer.flush(identity, local_index, now)
+                } else {
+                    Vec::new()
+                };
+
+                let peer_key = *peer.public_key();
+                (replies, self.check_packet(packet, &peer_key))
+            }
+            None => (Vec::new(), None),
+        }
+    }
+
+    /// Checks the decrypted packet from the peer.
+    ///
+    /// This method returns the packet without the padding, or `None` if the packet is a keepalive
+    /// message, is malformed, or has a source address that is not allowed for the peer.
+    fn check_packet(&self, mut packet: Vec<u8>, peer_key: &Key) -> Option<Vec<u8>> {
+        if packet.len() < IPV4_HEADER_LEN || packet[0] >> 4 != 4 {
+            return None;
+        }
+
+        let total_len = u16::from_be_bytes([packet[2], packet[3]]) as usize;
+        if !(IPV4_HEADER_LEN..=packet.len()).contains(&total_len) {
+            return None;
+        }
+
+        let src_addr = Ipv4Address::from(<[u8; 4]>::try_from(&packet[12..16]).unwrap());
+        if self.allowed_ips.lookup(&src_addr) != Some(peer_key) {
+            return None;
+        }
+
+        packet.truncate(total_len);
+        Some(packet)
+    }
+
+    fn remove_peer(&mut self, public_key: &Key) {
+        self.peers.retain(|peer| peer.public_key() != public_key);
+        self.allowed_ips.remove_peer(public_key);
+    }
+
+    /// Allocates an index to identify a handshake or a keypair of the iface.
+    fn alloc_index(&mut self) -> u32 {
+        loop {
+            let index = self.next_index;
+            self.next_index = self.next_index.wrapping_add(1);
+            if !self.peers.iter().any(|peer| peer.owns_index(index)) {
+                return index;
+            }
+        }
+    }
+}
+
+const IPV4_HEADER_LEN: usize = 20;
@@ -0,0 +1,370 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The attributes of perf events, which are specified in `perf_event_open`.
+
+use crate::prelude::*;
+
+/// The attributes of a perf event (`struct perf_event_attr`).
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/perf_event.h#L389>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub struct CPerfEventAttr {
+    pub type_: u32,
+    pub _size: u32,
+    pub config: u64,
+    /// The sample period, or the sample frequency if [`PerfEventFlags::FREQ`] is set.
+    pub sample_period_or_freq: u64,
+    pub sample_type: u64,
+    pub read_format: u64,
+    pub flags: u64,
+    /// The number of the samples, or the number of bytes if [`PerfEventFlags::WATERMARK`] is
+    /// set, before user space is woken up.
+    pub wakeup_events_or_watermark: u32,
+    pub _bp_type: u32,
+    pub _config1: u64,
+    pub _config2: u64,
+    pub _branch_sample_type: u64,
+    pub _sample_regs_user: u64,
+    pub _sample_stack_user: u32,
+    pub clockid: i32,
+    pub _sample_regs_intr: u64,
+    pub _aux_watermark: u32,
+    pub _sample_max_stack: u16,
+    pub _reserved_2: u16,
+    pub _aux_sample_size: u32,
+    pub _reserved_3: u32,
+    pub _sig_data: u64,
+    pub _config3: u64,
+}
+
+/// The size of the first published version of `struct perf_event_attr`.
+pub const PERF_ATTR_SIZE_VER0: u32 = 64;
+
+/// The types of perf events.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/perf_event.h#L29>.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+#[expect(non_camel_case_types)]
+pub enum PerfType {
+    PERF_TYPE_HARDWARE = 0,
+    PERF_TYPE_SOFTWARE = 1,
+    PERF_TYPE_TRACEPOINT = 2,
+    PERF_TYPE_HW_CACHE = 3,
+    PERF_TYPE_RAW = 4,
+    PERF_TYPE_BREAKPOINT = 5,
+}
+
+/// The generalized hardware events.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/perf_event.h#L53>.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+pub enum HwEvent {
+    CpuCycles = 0,
+    Instructions = 1,
+    CacheReferences = 2,
+    CacheMisses = 3,
+    BranchInstructions = 4,
+    BranchMisses = 5,
+    BusCycles = 6,
+    StalledCyclesFrontend = 7,
+    StalledCyclesBackend = 8,
+    RefCpuCycles = 9,
+}
+
+/// The software events.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/perf_event.h#L109>.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+pub enum SwEvent {
+    CpuClock = 0,
+    TaskClock = 1,
+    PageFaults = 2,
+    ContextSwitches = 3,
+    CpuMigrations = 4,
+    PageFaultsMin = 5,
+    PageFaultsMaj = 6,
+    AlignmentFaults = 7,
+    EmulationFaults = 8,
+    Dummy = 9,
+    BpfOutput = 10,
+    CgroupSwitches = 11,
+}
+
+impl SwEvent {
+    /// Returns whether the event counts the time in nanoseconds.
+    pub fn is_clock(self) -> bool {
+        matches!(self, Self::CpuClock | Self::TaskClock)
+    }
+}
+
+/// The kind of a perf event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PerfEventKind {
+    Hardware(HwEvent),
+    Software(SwEvent),
+}
+
+bitflags! {
+    /// The flags in `struct perf_event_attr`.
+    pub struct PerfEventFlags: u64 {
+        const DISABLED                 = 1 << 0;
+        const INHERIT                  = 1 << 1;
+        const PINNED                   = 1 << 2;
+        const EXCLUSIVE                = 1 << 3;
+        const EXCLUDE_USER             = 1 << 4;
+        const EXCLUDE_KERNEL           = 1 << 5;
+        const EXCLUDE_HV               = 1 << 6;
+        const EXCLUDE_IDLE             = 1 << 7;
+        const MMAP                     = 1 << 8;
+        const COMM                     = 1 << 9;
+        const FREQ                     = 1 << 10;
+        const INHERIT_STAT             = 1 << 11;
+        const ENABLE_ON_EXEC           = 1 << 12;
+        const TASK                     = 1 << 13;
+        const WATERMARK                = 1 << 14;
+        const PRECISE_IP               = 0b11 << 15;
+        const MMAP_DATA                = 1 << 17;
+        const SAMPLE_ID_ALL            = 1 << 18;
+        const EXCLUDE_HOST             = 1 << 19;
+        const EXCLUDE_GUEST            = 1 << 20;
+        const EXCLUDE_CALLCHAIN_KERNEL = 1 << 21;
+        const EXCLUDE_CALLCHAIN_USER   = 1 << 22;
+        const MMAP2                    = 1 << 23;
+        const COMM_EXEC                = 1 << 24;
+        const USE_CLOCKID              = 1 << 25;
+        const CONTEXT_SWITCH           = 1 << 26;
+        const WRITE_BACKWARD           = 1 << 27;
+        const NAMESPACES               = 1 << 28;
+        const KSYMBOL                  = 1 << 29;
+        const BPF_EVENT                = 1 << 30;
+        const AUX_OUTPUT               = 1 << 31;
+        const CGROUP                   = 1 << 32;
+        const TEXT_POKE                = 1 << 33;
+        const BUILD_ID                 = 1 << 34;
+        const INHERIT_THREAD           = 1 << 35;
+        const REMOVE_ON_EXEC           = 1 << 36;
+        const SIGTRAP                  = 1 << 37;
+    }
+}
+
+impl PerfEventFlags {
+    /// The flags that request side-band records (e.g., `PERF_RECORD_MMAP`).
+    ///
+    /// TODO: Generate the side-band records. For now, these flags are accepted so that tools
+    /// like `perf record` work, but no side-band records are generated.
+    const SIDE_BAND: Self = Self::MMAP
+        .union(Self::COMM)
+        .union(Self::TASK)
+        .union(Self::MMAP_DATA)
+        .union(Self::MMAP2)
+        .union(Self::COMM_EXEC)
+        .union(Self::CONTEXT_SWITCH)
+        .union(Self::NAMESPACES)
+        .union(Self::KSYMBOL)
+        .union(Self::BPF_EVENT)
+        .union(Self::CGROUP)
+        .union(Self::TEXT_POKE)
+        .union(Self::BUILD_ID);
+
+    /// The flags that are accepted but have no effects.
+    const IGNORED: Self = Self::PINNED
+        .union(Self::EXCLUSIVE)
+        .union(Self::EXCLUDE_HV)
+        .union(Self::EXCLUDE_IDLE)
+        .union(Self::INHERIT_STAT)
+        .union(Self::EXCLUDE_HOST)
+        .union(Self::EXCLUDE_GUEST)
+        .union(Self::EXCLUDE_CALLCHAIN_KERNEL)
+        .union(Self::EXCLUDE_CALLCHAIN_USER);
+
+    /// The flags that are supported.
+    const SUPPORTED: Self = Self::SIDE_BAND
+        .union(Self::IGNORED)
+        .union(Self::DISABLED)
+        .union(Self::INHERIT)
+        .union(Self::EXCLUDE_USER)
+        .union(Self::EXCLUDE_KERNEL)
+        .union(Self::FREQ)
+        .union(Self::ENABLE_ON_EXEC)
+        .union(Self::WATERMARK)
+        .union(Self::PRECISE_IP)
+        .union(Self::SAMPLE_ID_ALL)
+        .union(Self::USE_CLOCKID);
+}
+
+bitflags! {
+    /// The fields included in the samples.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/perf_event.h#L140>.
+    pub struct SampleType: u64 {
+        const IP             = 1 << 0;
+        const TID            = 1 << 1;
+        const TIME           = 1 << 2;
+        const ADDR           = 1 << 3;
+        const READ           = 1 << 4;
+        const CALLCHAIN      = 1 << 5;
+        const ID             = 1 << 6;
+        const CPU            = 1 << 7;
+        const PERIOD         = 1 << 8;
+        const STREAM_ID      = 1 << 9;
+        const RAW            = 1 << 10;
+        const BRANCH_STACK   = 1 << 11;
+        const REGS_USER      = 1 << 12;
+        const STACK_USER     = 1 << 13;
+        const WEIGHT         = 1 << 14;
+        const DATA_SRC       = 1 << 15;
+        const IDENTIFIER     = 1 << 16;
+        const TRANSACTION    = 1 << 17;
+        const REGS_INTR      = 1 << 18;
+        const PHYS_ADDR      = 1 << 19;
+        const AUX            = 1 << 20;
+        const CGROUP         = 1 << 21;
+        const DATA_PAGE_SIZE = 1 << 22;
+        const CODE_PAGE_SIZE = 1 << 23;
+        const WEIGHT_STRUCT  = 1 << 24;
+    }
+}
+
+impl SampleType {
+    /// The fields that are supported.
+    const SUPPORTED: Self = Self::IP
+        .union(Self::TID)
+        .union(Self::TIME)
+        .union(Self::ADDR)
+        .union(Self::ID)
+        .union(Self::CPU)
+        .union(Self::PERIOD)
+        .union(Self::STREAM_ID)
+        .union(Self::IDENTIFIER);
+}
+
+bitflags! {
+    /// The fields returned by reading perf events.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/perf_event.h#L347>.
+    pub struct ReadFormat: u64 {
+        const TOTAL_TIME_ENABLED = 1 << 0;
+        const TOTAL_TIME_RUNNING = 1 << 1;
+        const ID                 = 1 << 2;
+        const GROUP              = 1 << 3;
+        const LOST               = 1 << 4;
+    }
+}
+
+/// How a perf event is sampled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SamplePolicy {
+    /// The event is not sampled.
+    None,
+    /// A sample is taken every `period` events.
+    Period(u64),
+    /// Samples are taken at the frequency in Hz.
+    Freq(u64),
+}
+
+/// The validated attributes of a perf event.
+#[derive(Debug, Clone, Copy)]
+pub struct PerfEventAttr {
+    pub kind: PerfEventKind,
+    pub sample_policy: SamplePolicy,
+    pub sample_type: SampleType,
+    pub read_format: ReadFormat,
+    pub flags: PerfEventFlags,
+    pub wakeup_events_or_watermark: u32,
+}
+
+/// The clocks that can be used for the timestamps, which must be monotonic.
+const CLOCK_MONOTONIC: i32 = 1;
+const CLOCK_MONOTONIC_RAW: i32 = 4;
+
+impl TryFrom<&CPerfEventAttr> for PerfEventAttr {
+    type Error = Error;
+
+    fn try_from(c_attr: &CPerfEventAttr) -> Result<Self> {
+        let Some(flags) = PerfEventFlags::from_bits(c_attr.flags) else {
+            return_errno_with_message!(Errno::EINVAL, "the flags are invalid");
+        };
+        if !PerfEventFlags::SUPPORTED.contains(flags) {
+            return_errno_with_message!(Errno::EINVAL, "the flags are not supported");
+        }
+        if flags.contains(PerfEventFlags::USE_CLOCKID)
+            && c_attr.clockid != CLOCK_MONOTONIC
+            && c_attr.clockid != CLOCK_MONOTONIC_RAW
+        {
+            return_errno_with_message!(Errno::EINVAL, "the clock is not supported");
+        }
+
+        let Some(sample_type) = SampleType::from_bits(c_attr.sample_type) else {
+            return_errno_with_message!(Errno::EINVAL, "the sample type is invalid");
+        };
+        if !SampleType::SUPPORTED.contains(sample_type) {
+            return_errno_with_message!(Errno::EINVAL, "the sample type is not supported");
+        }
+        let Some(read_format) = ReadFormat::from_bits(c_attr.read_format) else {
+            return_errno_with_message!(Errno::EINVAL, "the read format is invalid");
+        };
+
+        let sample_policy = match c_attr.sample_period_or_freq {
+            0 => SamplePolicy::None,
+            freq if flags.contains(PerfEventFlags::FREQ) => SamplePolicy::Freq(freq),
+            period if period as i64 >= 0 => SamplePolicy::Period(period),
+            _ => return_errno_with_message!(Errno::EINVAL, "the sample period is too large"),
+        };
+
+        let kind = parse_kind(c_attr)?;
+        if let PerfEventKind::Hardware(_) = kind {
+            if sample_policy != SamplePolicy::None {
+                // TODO: Support sampling hardware events with the overflow interrupts.
+                return_errno_with_message!(Errno::EOPNOTSUPP, "hardware events cannot be sampled");
+            }
+            if flags.intersects(PerfEventFlags::PRECISE_IP) {
+                return_errno_with_message!(Errno::EOPNOTSUPP, "precise IPs are not supported");
+            }
+        }
+
+        Ok(Self {
+            kind,
+            sample_policy,
+            sample_type,
+            read_format,
+            flags,
+            wakeup_events_or_watermark: c_attr.wakeup_events_or_watermark,
+        })
+    }
+}
+
+fn parse_kind(c_attr: &CPerfEventAttr) -> Result<PerfEventKind> {
+    let perf_type = PerfType::try_from(c_attr.type_)
+        .map_err(|_| Error::with_message(Errno::ENOENT, "the event type is not supported"))?;
+
+    // The upper 32 bits of the config select the PMU on hybrid systems, which is not supported.
+    let Ok(config) = u32::try_from(c_attr.config) else {
+        return_errno_with_message!(Errno::ENOENT, "the event config is not supported");
+    };
+
+    let kind = match perf_type {
+        PerfType::PERF_TYPE_HARDWARE => {
+            let hw_event = HwEvent::try_from(config)
+                .map_err(|_| Error::with_message(Errno::ENOENT, "the event is not supported"))?;
+            if !crate::arch::pmu::is_event_supported(hw_event) {
+                return_errno_with_message!(Errno::ENOENT, "the event is not supported by the PMU");
+            }
+            PerfEventKind::Hardware(hw_event)
+        }
+        PerfType::PERF_TYPE_SOFTWARE => {
+            let sw_event = SwEvent::try_from(config)
+                .map_err(|_| Error::with_message(Errno::ENOENT, "the event is not supported"))?;
+            if matches!(sw_event, SwEvent::BpfOutput | SwEvent::CgroupSwitches) {
+                return_errno_with_message!(Errno::ENOENT, "the event is not supported");
+            }
+            PerfEventKind::Software(sw_event)
+        }
+        _ => return_errno_with_message!(Errno::ENOENT, "the event type is not supported"),
+    };
+
+    Ok(kind)
+}
@@ -0,0 +1,218 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use ostd::{
+    cpu::{CpuId, PinCurrentCpu},
+    irq::{DisabledLocalIrqGuard, disable_local},
+    sync::LocalIrqDisabled,
+};
+
+use super::{
+    attr::PerfEventFlags,
+    event::{PendingSample, PerfEvent},
+    hw, now_ns,
+};
+use crate::prelude::*;
+
+/// The perf events that are attached to a thread.
+pub struct PerfEventContext {
+    inner: SpinLock<ContextInner, LocalIrqDisabled>,
+    has_pending_samples: AtomicBool,
+}
+
+struct ContextInner {
+    entries: Vec<ContextEntry>,
+    /// The CPU where the thread is running, or `None` if the thread is not running.
+    running_cpu: Option<CpuId>,
+    /// The CPU where the thread ran last time, which is used to detect CPU migrations.
+    last_cpu: Option<CpuId>,
+    pending_samples: Vec<PendingSample>,
+}
+
+struct ContextEntry {
+    event: Weak<PerfEvent>,
+    /// Whether the event is scheduled on the CPU where the thread is running.
+    is_scheduled: bool,
+}
+
+/// The maximum number of the samples that are pending in a thread.
+///
+/// The samples are written when the thread returns to user space, so there should not be many of
+/// them. If there are more, they are lost.
+const MAX_PENDING_SAMPLES: usize = 64;
+
+impl PerfEventContext {
+    /// Creates a context without any events.
+    pub fn new() -> Self {
+        Self {
+            inner: SpinLock::new(ContextInner {
+                entries: Vec::new(),
+                running_cpu: None,
+                last_cpu: None,
+                pending_samples: Vec::new(),
+            }),
+            has_pending_samples: AtomicBool::new(false),
+        }
+    }
+
+    /// Creates a context for a new thread that is created by the thread.
+    ///
+    /// The events that should be inherited are attached to the new thread as well. Unlike Linux,
+    /// the inherited events are not cloned, so the new thread counts to the same events.
+    pub fn inherit(&self) -> Self {
+        let inner = self.inner.lock();
+        let entries = inner
+            .entries
+            .iter()
+            .filter(|entry| {
+                entry.event.upgrade().is_some_and(|event| {
+                    event.attr().flags.contains(PerfEventFlags::INHERIT)
+                })
+            })
+            .map(|entry| ContextEntry {
+                event: entry.event.clone(),
+                is_scheduled: false,
+            })
+            .collect();
+        drop(inner);
+
+        let new_context = Self::new();
+        new_context.inner.lock().entries = entries;
+        new_context
+    }
+
+    /// Attaches the event to the thread.
+    ///
+    /// If the thread is running on the current CPU, the event is scheduled immediately.
+    /// Otherwise, the event is scheduled when the thread is switched in or at the next timer
+    /// interrupt on the CPU where the thread is running.
+    pub fn attach(&self, event: &Arc<PerfEvent>) {
+        let irq_guard = disable_local();
+        let mut inner = self.inner.lock();
+        inner.entries.push(ContextEntry {
+            event: Arc::downgrade(event),
+            is_scheduled: false,
+        });
+        if inner.running_cpu == Some(irq_guard.current_cpu()) {
+            schedule_entries(&mut inner, now_ns(), &irq_guard);
+        }
+    }
+
+    /// Schedules the events after the thread is switched in on the current CPU.
+    ///
+    /// This method returns whether the thread is migrated from another CPU.
+    pub(super) fn switch_in(&self, now: u64, irq_guard: &DisabledLocalIrqGuard) -> bool {
+        let cpu = irq_guard.current_cpu();
+        let mut inner = self.inner.lock();
+        inner.running_cpu = Some(cpu);
+        let is_migrated = inner.last_cpu.is_some_and(|last_cpu| last_cpu != cpu);
+        inner.last_cpu = Some(cpu);
+        schedule_entries(&mut inner, now, irq_guard);
+        is_migrated
+    }
+
+    /// Unschedules the events before the thread is switched out.
+    ///
+    /// The hardware counters should have been stopped with [`hw::stop_thread_counters`].
+    pub(super) fn switch_out(&self, now: u64) {
+        let mut inner = self.inner.lock();
+        inner.running_cpu = None;
+        for entry in inner.entries.iter_mut() {
+            if !entry.is_scheduled {
+                continue;
+            }
+            entry.is_scheduled = false;
+            if let Some(event) = entry.event.upgrade() {
+                event.mark_unscheduled(now);
+            }
+        }
+    }
+
+    /// Schedules the events that are attached after the thread is switched in on the current CPU.
+    pub(super) fn schedule(&self, now: u64, irq_guard: &DisabledLocalIrqGuard) {
+        let mut inner = self.inner.lock();
+        if inner.running_cpu == Some(irq_guard.current_cpu()) {
+            schedule_entries(&mut inner, now, irq_guard);
+        }
+    }
+
+    /// Calls the function for each event that is scheduled.
+    pub(super) fn for_each_scheduled(&self, mut f: impl FnMut(&Arc<PerfEvent>)) {
+        let mut inner = self.inner.lock();
+        inner.entries.retain(|entry| entry.event.strong_count() > 0);
+        inner
+            .entries
+            .iter()
+            .filter(|entry| entry.is_scheduled)
+            .filter_map(|entry| entry.event.upgrade())
+            .for_each(|event| f(&event));
+    }
+
+    /// Enables the events that should be enabled when the thread calls `execve`.
+    pub fn enable_on_exec(&self) {
+        let events: Vec<_> = {
+            let inner = self.inner.lock();
+            inner
+                .entries
+                .iter()
+                .filter_map(|entry| entry.event.upgrade())
+                .collect()
+        };
+        for event in events {
+            event.enable_on_exec();
+        }
+    }
+
+    /// Queues the samples, which will be written when the thread returns to user space.
+    pub(super) fn queue_samples(&self, samples: Vec<PendingSample>) {
+        let mut inner = self.inner.lock();
+        for sample in samples {
+            if inner.pending_samples.len() >= MAX_PENDING_SAMPLES {
+                sample.event.count_lost_sample();
+                continue;
+            }
+            inner.pending_samples.push(sample);
+        }
+        self.has_pending_samples.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether there are samples that should be written.
+    pub fn has_pending_samples(&self) -> bool {
+        self.has_pending_samples.load(Ordering::Relaxed)
+    }
+
+    /// Takes the samples that should be written.
+    pub(super) fn take_pending_samples(&self) -> Vec<PendingSample> {
+        let mut inner = self.inner.lock();
+        self.has_pending_samples.store(false, Ordering::Relaxed);
+        core::mem::take(&mut inner.pending_samples)
+    }
+}
+
+impl Default for PerfEventContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Schedules the events that can count on the current CPU, where the thread is running.
+///
+/// The hardware counters are also started for the scheduled hardware events.
+fn schedule_entries(inner: &mut ContextInner, now: u64, irq_guard: &DisabledLocalIrqGuard) {
+    let cpu = irq_guard.current_cpu();
+    for entry in inner.entries.iter_mut() {
+        let Some(event) = entry.event.upgrade() else {
+            continue;
+        };
+        if event.cpu().is_some_and(|event_cpu| event_cpu != cpu) {
+            continue;
+        }
+
+        if !entry.is_scheduled {
+            entry.is_scheduled = true;
+            event.mark_scheduled(now);
+        }
+        hw::start_counter(&event, false, irq_guard);
+    }
+}
@@ -0,0 +1,650 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use ostd::{cpu::CpuId, sync::LocalIrqDisabled, util::id_set::Id};
+
+use super::{
+    attr::{
+        PerfEventAttr, PerfEventFlags, PerfEventKind, ReadFormat, SamplePolicy, SampleType,
+        SwEvent,
+    },
+    now_ns,
+    ring_buffer::{RingBuffer, Wakeup},
+};
+use crate::{events::IoEvents, prelude::*, process::signal::PollHandle};
+
+/// A perf event, which counts the occurrences of a hardware or software event.
+///
+/// An event is either attached to threads, in which case it only counts when the threads are
+/// running, or attached to a CPU, in which case it counts whatever runs on the CPU. It can also
+/// be sampled, in which case the samples are written to its ring buffer.
+pub struct PerfEvent {
+    attr: PerfEventAttr,
+    id: u64,
+    /// The CPU where the event counts, or `None` if the event counts on all CPUs.
+    cpu: Option<CpuId>,
+    is_enabled: AtomicBool,
+    /// The group leader, or `None` if the event is a group leader itself.
+    leader: Option<Arc<PerfEvent>>,
+    /// The other events in the group whose leader is the event.
+    siblings: SpinLock<Vec<Weak<PerfEvent>>>,
+    state: SpinLock<EventState, LocalIrqDisabled>,
+    /// The number of the samples that are lost because the ring buffer is full.
+    nr_lost: AtomicU64,
+    /// The number of the lost samples that are not reported with `PERF_RECORD_LOST`.
+    nr_unreported_lost: AtomicU64,
+    output: Mutex<Output>,
+}
+
+struct EventState {
+    count: u64,
+    /// The count when the last sample is taken.
+    last_sample_count: u64,
+    sample_policy: SamplePolicy,
+    /// The number of the CPUs where the event is scheduled, i.e., the CPU is running a thread
+    /// that the event is attached to, or the event is attached to the CPU.
+    nr_scheduled: u32,
+    /// The number of the CPUs where the event is actually counting.
+    ///
+    /// A hardware event may be scheduled but not counting if there are no free hardware
+    /// counters.
+    nr_running: u32,
+    time_enabled: u64,
+    time_running: u64,
+    /// The time when the times are last updated.
+    time_updated: u64,
+}
+
+/// Where the samples of a perf event are written.
+enum Output {
+    /// The samples are discarded because no ring buffer has been mapped.
+    None,
+    /// The samples are written to the ring buffer of the event itself.
+    Own(Arc<RingBuffer>),
+    /// The samples are written to the ring buffer of another event.
+    Redirected(Arc<RingBuffer>),
+}
+
+/// A sample that is taken but not written to the ring buffer yet.
+///
+/// Samples are taken when the events happen, where the user-space context may not be available.
+/// So they are written when the thread returns to user space.
+pub(super) struct PendingSample {
+    pub(super) event: Arc<PerfEvent>,
+    /// The number of the events since the last sample.
+    pub(super) period: u64,
+    /// The address that is related to the sample, e.g., the faulting address of page faults.
+    pub(super) addr: Vaddr,
+    pub(super) time: u64,
+    pub(super) cpu: CpuId,
+}
+
+/// The types and the miscellaneous bits of the records.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/perf_event.h#L857>.
+const PERF_RECORD_LOST: u32 = 2;
+const PERF_RECORD_SAMPLE: u32 = 9;
+const PERF_RECORD_MISC_USER: u16 = 2;
+
+const NSEC_PER_SEC: u64 = 1_000_000_000;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+impl PerfEvent {
+    /// Creates a perf event that counts on the CPU, or on all CPUs if `cpu` is `None`.
+    ///
+    /// If `is_per_cpu` is true, the event is attached to the CPU and counts whatever runs on it.
+    /// Otherwise, the event should be attached to threads with [`PerfEventContext::attach`].
+    ///
+    /// [`PerfEventContext::attach`]: super::PerfEventContext::attach
+    pub fn new(
+        attr: PerfEventAttr,
+        cpu: Option<CpuId>,
+        is_per_cpu: bool,
+        leader: Option<Arc<PerfEvent>>,
+    ) -> Arc<Self> {
+        let (nr_scheduled, nr_running) = match (is_per_cpu, attr.kind) {
+            (false, _) => (0, 0),
+            // Hardware events start running when the hardware counters are allocated.
+            (true, PerfEventKind::Hardware(_)) => (1, 0),
+            (true, PerfEventKind::Software(_)) => (1, 1),
+        };
+
+        let event = Arc::new(Self {
+            attr,
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            cpu,
+            is_enabled: AtomicBool::new(!attr.flags.contains(PerfEventFlags::DISABLED)),
+            leader,
+            siblings: SpinLock::new(Vec::new()),
+            state: SpinLock::new(EventState {
+                count: 0,
+                last_sample_count: 0,
+                sample_policy: attr.sample_policy,
+                nr_scheduled,
+                nr_running,
+                time_enabled: 0,
+                time_running: 0,
+                time_updated: now_ns(),
+            }),
+            nr_lost: AtomicU64::new(0),
+            nr_unreported_lost: AtomicU64::new(0),
+            output: Mutex::new(Output::None),
+        });
+
+        if let Some(leader) = event.leader.as_ref() {
+            leader.siblings.lock().push(Arc::downgrade(&event));
+        }
+
+        event
+    }
+
+    /// Returns the attributes.
+    pub(super) fn attr(&self) -> &PerfEventAttr {
+        &self.attr
+    }
+
+    /// Returns the unique ID.
+    pub(super) fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the CPU where the event counts, or `None` if the event counts on all CPUs.
+    pub fn cpu(&self) -> Option<CpuId> {
+        self.cpu
+    }
+
+    /// Returns whether the event is a group leader.
+    pub fn is_leader(&self) -> bool {
+        self.leader.is_none()
+    }
+
+    /// Returns whether the event is a software event of the kind.
+    pub(super) fn is_software(&self, sw_event: SwEvent) -> bool {
+        self.attr.kind == PerfEventKind::Software(sw_event)
+    }
+
+    /// Returns whether the event is a software event that counts the time in nanoseconds.
+    pub(super) fn is_clock(&self) -> bool {
+        matches!(self.attr.kind, PerfEventKind::Software(sw_event) if sw_event.is_clock())
+    }
+
+    /// Returns whether the event is enabled.
+    ///
+    /// An event that is not a group leader is enabled only if its group leader is enabled.
+    pub(super) fn is_enabled(&self) -> bool {
+        self.is_enabled.load(Ordering::Relaxed)
+            && self
+                .leader
+                .as_ref()
+                .is_none_or(|leader| leader.is_enabled.load(Ordering::Relaxed))
+    }
+
+    /// Enables or disables the event, and also the other events in the group if `is_group` is
+    /// true.
+    pub(super) fn set_enabled(&self, is_enabled: bool, is_group: bool) {
+        let now = now_ns();
+
+        // Whether the other events in the group are enabled depends on whether the group leader
+        // is enabled, so their times should also be updated.
+        let siblings = self.siblings();
+        self.update_times(now);
+        for sibling in siblings.iter() {
+            sibling.update_times(now);
+        }
+
+        self.is_enabled.store(is_enabled, Ordering::Relaxed);
+        if is_group {
+            for sibling in siblings.iter() {
+                sibling.is_enabled.store(is_enabled, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Enables the event if it should be enabled when the thread calls `execve`.
+    pub(super) fn enable_on_exec(&self) {
+        let flags = self.attr.flags;
+        if flags.contains(PerfEventFlags::ENABLE_ON_EXEC | PerfEventFlags::DISABLED) {
+            self.set_enabled(true, false);
+        }
+    }
+
+    /// Resets the count to zero, and also the counts of the other events in the group if
+    /// `is_group` is true.
+    pub(super) fn reset(&self, is_group: bool) {
+        self.reset_count();
+        if is_group {
+            for sibling in self.siblings().iter() {
+                sibling.reset_count();
+            }
+        }
+    }
+
+    fn reset_count(&self) {
+        let mut state = self.state.lock();
+        state.count = 0;
+        state.last_sample_count = 0;
+    }
+
+    /// Sets the sample period, or the sample frequency if the event is sampled by the frequency.
+    pub(super) fn set_sample_period(&self, period_or_freq: u64) -> Result<()> {
+        if period_or_freq as i64 <= 0 {
+            return_errno_with_message!(Errno::EINVAL, "the sample period is invalid");
+        }
+
+        let mut state = self.state.lock();
+        state.sample_policy = match state.sample_policy {
+            SamplePolicy::None => {
+                return_errno_with_message!(Errno::EINVAL, "the event is not sampled")
+            }
+            SamplePolicy::Period(_) => SamplePolicy::Period(period_or_freq),
+            SamplePolicy::Freq(_) => SamplePolicy::Freq(period_or_freq),
+        };
+        Ok(())
+    }
+
+    /// Marks the event as scheduled on a CPU.
+    ///
+    /// A software event starts counting when it is scheduled, while a hardware event starts
+    /// counting when a hardware counter is allocated (see [`Self::mark_counter_started`]).
+    pub(super) fn mark_scheduled(&self, now: u64) {
+        let mut state = self.state.lock();
+        self.update_times_locked(&mut state, now);
+        state.nr_scheduled += 1;
+        if let PerfEventKind::Software(_) = self.attr.kind {
+            state.nr_running += 1;
+        }
+    }
+
+    /// Marks the event as no longer scheduled on a CPU.
+    pub(super) fn mark_unscheduled(&self, now: u64) {
+        let mut state = self.state.lock();
+        self.update_times_locked(&mut state, now);
+        state.nr_scheduled -= 1;
+        if let PerfEventKind::Software(_) = self.attr.kind {
+            state.nr_running -= 1;
+        }
+    }
+
+    /// Marks the hardware event as counting with a hardware counter.
+    pub(super) fn mark_counter_started(&self, now: u64) {
+        let mut state = self.state.lock();
+        self.update_times_locked(&mut state, now);
+        state.nr_running += 1;
+    }
+
+    /// Marks the hardware event as no longer counting with a hardware counter.
+    pub(super) fn mark_counter_stopped(&self, now: u64) {
+        let mut state = self.state.lock();
+        self.update_times_locked(&mut state, now);
+        state.nr_running -= 1;
+    }
+
+    /// Adds the number of the events that happen.
+    ///
+    /// If the events happen in user mode (kernel mode), `is_user` should be true (false), so they
+    /// can be excluded according to the attributes. Hardware counters count in the modes
+    /// according to the attributes, so they should call [`Self::add_hw_count`] instead.
+    ///
+    /// This method returns the sample period if a sample should be taken.
+    pub(super) fn add_count(&self, count: u64, is_user: bool) -> Option<u64> {
+        if self.is_excluded(is_user) {
+            return None;
+        }
+        self.add_hw_count(count)
+    }
+
+    /// Adds the number of the events that are counted by a hardware counter.
+    pub(super) fn add_hw_count(&self, count: u64) -> Option<u64> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        let mut state = self.state.lock();
+        state.count += count;
+        self.check_sample(&mut state)
+    }
+
+    /// Updates the times and the count of the clock event at the timer interrupt.
+    ///
+    /// This method returns the sample period if a sample should be taken.
+    pub(super) fn tick_clock(&self, now: u64, is_user: bool) -> Option<u64> {
+        let mut state = self.state.lock();
+        self.update_times_locked(&mut state, now);
+        if self.is_excluded(is_user) {
+            return None;
+        }
+        self.check_sample(&mut state)
+    }
+
+    /// Returns whether the events in the mode are excluded from counting.
+    fn is_excluded(&self, is_user: bool) -> bool {
+        let exclude_flag = if is_user {
+            PerfEventFlags::EXCLUDE_USER
+        } else {
+            PerfEventFlags::EXCLUDE_KERNEL
+        };
+        self.attr.flags.contains(exclude_flag)
+    }
+
+    fn check_sample(&self, state: &mut EventState) -> Option<u64> {
+        let period = match state.sample_policy {
+            SamplePolicy::None => return None,
+            SamplePolicy::Period(period) => period,
+            SamplePolicy::Freq(freq) if self.is_clock() => (NSEC_PER_SEC / freq).max(1),
+            // TODO: Adjust the period dynamically to meet the frequency, like Linux does. For
+            // now, every event is sampled.
+            SamplePolicy::Freq(_) => 1,
+        };
+
+        let elapsed = state.count - state.last_sample_count;
+        if elapsed < period {
+            return None;
+        }
+        state.last_sample_count = state.count;
+        Some(elapsed)
+    }
+
+    fn update_times(&self, now: u64) {
+        let mut state = self.state.lock();
+        self.update_times_locked(&mut state, now);
+    }
+
+    fn update_times_locked(&self, state: &mut EventState, now: u64) {
+        let elapsed = now.saturating_sub(state.time_updated);
+        state.time_updated = now;
+        if !self.is_enabled() {
+            return;
+        }
+
+        if state.nr_scheduled > 0 {
+            state.time_enabled += elapsed;
+        }
+        if state.nr_running > 0 {
+            state.time_running += elapsed;
+            // Clock events count the time in nanoseconds.
+            if self.is_clock() {
+                state.count += elapsed;
+            }
+        }
+    }
+
+    /// Reads the count and the times when the event is enabled and running.
+    fn read_values(&self) -> (u64, u64, u64) {
+        let mut state = self.state.lock();
+        self.update_times_locked(&mut state, now_ns());
+        (state.count, state.time_enabled, state.time_running)
+    }
+
+    /// Returns the group leader and the other events in the group.
+    fn group(&self) -> (&PerfEvent, Vec<Arc<PerfEvent>>) {
+        let leader = self.leader.as_deref().unwrap_or(self);
+        (leader, leader.siblings())
+    }
+
+    fn siblings(&self) -> Vec<Arc<PerfEvent>> {
+        let mut siblings = self.siblings.lock();
+        siblings.retain(|sibling| sibling.strong_count() > 0);
+        siblings.iter().filter_map(Weak::upgrade).collect()
+    }
+
+    /// Reads the values of the event according to the read format.
+    pub(super) fn read(&self, writer: &mut VmWriter) -> Result<usize> {
+        let read_format = self.attr.read_format;
+        let mut values = Vec::new();
+
+        if read_format.contains(ReadFormat::GROUP) {
+            let (leader, siblings) = self.group();
+            let (count, time_enabled, time_running) = leader.read_values();
+            values.push(1 + siblings.len() as u64);
+            push_times(&mut values, read_format, time_enabled, time_running);
+            values.push(count);
+            leader.push_id_and_lost(&mut values, read_format);
+            for sibling in siblings.iter() {
+                values.push(sibling.read_values().0);
+                sibling.push_id_and_lost(&mut values, read_format);
+            }
+        } else {
+            let (count, time_enabled, time_running) = self.read_values();
+            values.push(count);
+            push_times(&mut values, read_format, time_enabled, time_running);
+            self.push_id_and_lost(&mut values, read_format);
+        }
+
+        let bytes = values.as_slice().as_bytes();
+        if writer.avail() < bytes.len() {
+            return_errno_with_message!(Errno::ENOSPC, "the buffer is too small");
+        }
+        writer.write_fallible(&mut bytes.into())?;
+        Ok(bytes.len())
+    }
+
+    fn push_id_and_lost(&self, values: &mut Vec<u64>, read_format: ReadFormat) {
+        if read_format.contains(ReadFormat::ID) {
+            values.push(self.id);
+        }
+        if read_format.contains(ReadFormat::LOST) {
+            values.push(self.nr_lost.load(Ordering::Relaxed));
+        }
+    }
+
+    /// Creates the ring buffer that is mapped with the size, or checks the size if the ring
+    /// buffer exists.
+    pub(super) fn setup_ring_buffer(&self, size: usize) -> Result<Arc<RingBuffer>> {
+        let mut output = self.output.lock();
+        match &*output {
+            Output::Own(ring_buffer) if ring_buffer.size() == size => {
+                return Ok(ring_buffer.clone());
+            }
+            Output::Own(_) => {
+                return_errno_with_message!(Errno::EINVAL, "the ring buffer size mismatches")
+            }
+            Output::Redirected(_) => {
+                return_errno_with_message!(Errno::EINVAL, "the output is redirected")
+            }
+            Output::None => (),
+        }
+
+        let data_size = size.saturating_sub(PAGE_SIZE) as u64;
+        let wakeup_events_or_watermark = self.attr.wakeup_events_or_watermark;
+        let wakeup = if self.attr.flags.contains(PerfEventFlags::WATERMARK) {
+            match wakeup_events_or_watermark {
+                0 => Wakeup::Bytes(data_size / 2),
+                watermark => Wakeup::Bytes(watermark as u64),
+            }
+        } else {
+            match wakeup_events_or_watermark {
+                0 => Wakeup::Bytes(data_size / 2),
+                nr_events => Wakeup::Records(nr_events),
+            }
+        };
+
+        let ring_buffer = Arc::new(RingBuffer::new(size, wakeup)?);
+        *output = Output::Own(ring_buffer.clone());
+        Ok(ring_buffer)
+    }
+
+    /// Returns the ring buffer of the event itself, if it has been set up.
+    pub(super) fn ring_buffer(&self) -> Option<Arc<RingBuffer>> {
+        match &*self.output.lock() {
+            Output::Own(ring_buffer) => Some(ring_buffer.clone()),
+            Output::None | Output::Redirected(_) => None,
+        }
+    }
+
+    /// Redirects the samples to the ring buffer of another event, or stops redirecting them if
+    /// `target` is `None`.
+    pub(super) fn set_output(&self, target: Option<&PerfEvent>) -> Result<()> {
+        if target.is_some_and(|target| core::ptr::eq(target, self)) {
+            return Ok(());
+        }
+
+        let new_output = match target {
+            Some(target) => match &*target.output.lock() {
+                Output::Own(ring_buffer) | Output::Redirected(ring_buffer) => {
+                    Output::Redirected(ring_buffer.clone())
+                }
+                Output::None => {
+                    return_errno_with_message!(Errno::EINVAL, "the target has no ring buffer")
+                }
+            },
+            None => Output::None,
+        };
+
+        let mut output = self.output.lock();
+        if let Output::Own(_) = &*output {
+            return_errno_with_message!(Errno::EBUSY, "the ring buffer of the event is mapped");
+        }
+        *output = new_output;
+        Ok(())
+    }
+
+    /// Polls the event, which is readable if its ring buffer has records not consumed.
+    pub(super) fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        match &*self.output.lock() {
+            Output::Own(ring_buffer) => ring_buffer.poll(mask, poller),
+            Output::None | Output::Redirected(_) => IoEvents::empty(),
+        }
+    }
+
+    /// Writes the sample to the ring buffer.
+    ///
+    /// The sample is taken in the user-space context with the instruction pointer and the IDs.
+    pub(super) fn output_sample(&self, sample: &PendingSample, ip: Vaddr, pid: u32, tid: u32) {
+        let output = self.output.lock();
+        let (Output::Own(ring_buffer) | Output::Redirected(ring_buffer)) = &*output else {
+            return;
+        };
+
+        let sample_type = self.attr.sample_type;
+        let cpu = sample.cpu.as_usize() as u32;
+
+        let nr_unreported_lost = self.nr_unreported_lost.load(Ordering::Relaxed);
+        if nr_unreported_lost > 0 {
+            let mut record = Record::new(PERF_RECORD_LOST, 0);
+            record.push_u64(self.id);
+            record.push_u64(nr_unreported_lost);
+            if self.attr.flags.contains(PerfEventFlags::SAMPLE_ID_ALL) {
+                self.push_sample_id(&mut record, pid, tid, sample.time, cpu);
+            }
+            if ring_buffer.write_record(record.finish()) {
+                self.nr_unreported_lost
+                    .fetch_sub(nr_unreported_lost, Ordering::Relaxed);
+            }
+        }
+
+        let mut record = Record::new(PERF_RECORD_SAMPLE, PERF_RECORD_MISC_USER);
+        if sample_type.contains(SampleType::IDENTIFIER) {
+            record.push_u64(self.id);
+        }
+        if sample_type.contains(SampleType::IP) {
+            record.push_u64(ip as u64);
+        }
+        if sample_type.contains(SampleType::TID) {
+            record.push_u32_pair(pid, tid);
+        }
+        if sample_type.contains(SampleType::TIME) {
+            record.push_u64(sample.time);
+        }
+        if sample_type.contains(SampleType::ADDR) {
+            record.push_u64(sample.addr as u64);
+        }
+        if sample_type.contains(SampleType::ID) {
+            record.push_u64(self.id);
+        }
+        if sample_type.contains(SampleType::STREAM_ID) {
+            record.push_u64(self.id);
+        }
+        if sample_type.contains(SampleType::CPU) {
+            record.push_u32_pair(cpu, 0);
+        }
+        if sample_type.contains(SampleType::PERIOD) {
+            record.push_u64(sample.period);
+        }
+
+        if !ring_buffer.write_record(record.finish()) {
+            self.nr_lost.fetch_add(1, Ordering::Relaxed);
+            self.nr_unreported_lost.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let (_, time_enabled, time_running) = self.read_values();
+        ring_buffer.update_times(time_enabled, time_running);
+    }
+
+    /// Pushes the fields that identify the sample (`struct sample_id`) to a record that is not a
+    /// sample.
+    fn push_sample_id(&self, record: &mut Record, pid: u32, tid: u32, time: u64, cpu: u32) {
+        let sample_type = self.attr.sample_type;
+        if sample_type.contains(SampleType::TID) {
+            record.push_u32_pair(pid, tid);
+        }
+        if sample_type.contains(SampleType::TIME) {
+            record.push_u64(time);
+        }
+        if sample_type.contains(SampleType::ID) {
+            record.push_u64(self.id);
+        }
+        if sample_type.contains(SampleType::STREAM_ID) {
+            record.push_u64(self.id);
+        }
+        if sample_type.contains(SampleType::CPU) {
+            record.push_u32_pair(cpu, 0);
+        }
+        if sample_type.contains(SampleType::IDENTIFIER) {
+            record.push_u64(self.id);
+        }
+    }
+
+    /// Counts a sample that is lost before it is written to the ring buffer.
+    pub(super) fn count_lost_sample(&self) {
+        self.nr_lost.fetch_add(1, Ordering::Relaxed);
+        self.nr_unreported_lost.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A record to be written to the ring buffer.
+///
+/// A record starts with a header (`struct perf_event_header`), which contains the type, the
+/// miscellaneous bits, and the size of the record.
+struct Record(Vec<u8>);
+
+impl Record {
+    fn new(type_: u32, misc: u16) -> Self {
+        let mut bytes = Vec::with_capacity(64);
+        bytes.extend_from_slice(&type_.to_ne_bytes());
+        bytes.extend_from_slice(&misc.to_ne_bytes());
+        // The size will be filled when the record is finished.
+        bytes.extend_from_slice(&0u16.to_ne_bytes());
+        Self(bytes)
+    }
+
+    fn push_u64(&mut self, val: u64) {
+        self.0.extend_from_slice(&val.to_ne_bytes());
+    }
+
+    fn push_u32_pair(&mut self, val0: u32, val1: u32) {
+        self.0.extend_from_slice(&val0.to_ne_bytes());
+        self.0.extend_from_slice(&val1.to_ne_bytes());
+    }
+
+    fn finish(&mut self) -> &[u8] {
+        let size = self.0.len() as u16;
+        self.0[6..8].copy_from_slice(&size.to_ne_bytes());
+        &self.0
+    }
+}
+
+fn push_times(
+    values: &mut Vec<u64>,
+    read_format: ReadFormat,
+    time_enabled: u64,
+    time_running: u64,
+) {
+    if read_format.contains(ReadFormat::TOTAL_TIME_ENABLED) {
+        values.push(time_enabled);
+    }
+    if read_format.contains(ReadFormat::TOTAL_TIME_RUNNING) {
+        values.push(time_running);
+    }
+}
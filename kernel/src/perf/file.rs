@@ -0,0 +1,186 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The files of perf events, which are returned by the `perf_event_open` system call.
+
+use core::fmt::Display;
+
+use ostd::{irq::disable_local, task::Task};
+
+use super::{event::PerfEvent, hw};
+use crate::{
+    events::IoEvents,
+    fs::{
+        file::{CreationFlags, FileLike, Mappable, file_table::FdFlags},
+        pseudofs::AnonInodeFs,
+        vfs::path::Path,
+    },
+    prelude::*,
+    process::{
+        posix_thread::AsThreadLocal,
+        signal::{PollHandle, Pollable},
+    },
+    util::ioctl::{RawIoctl, dispatch_ioctl},
+};
+
+/// The file of a perf event.
+pub struct PerfEventFile {
+    event: Arc<PerfEvent>,
+    pseudo_path: Path,
+}
+
+/// The flag of the ioctl commands that applies the commands to all the events in the group.
+const PERF_IOC_FLAG_GROUP: u32 = 1;
+
+impl PerfEventFile {
+    pub fn new(event: Arc<PerfEvent>) -> Self {
+        Self {
+            event,
+            pseudo_path: AnonInodeFs::new_path(|_| "anon_inode:[perf_event]".to_string()),
+        }
+    }
+
+    /// Returns the event.
+    pub fn event(&self) -> &Arc<PerfEvent> {
+        &self.event
+    }
+
+    /// Sets up the ring buffer that is mapped with the length and the offset.
+    ///
+    /// This method should be called before the file is mapped.
+    pub fn setup_ring_buffer(&self, len: usize, offset: usize) -> Result<()> {
+        if offset != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the ring buffer must be mapped at zero");
+        }
+        self.event.setup_ring_buffer(len)?;
+        Ok(())
+    }
+
+    fn set_output(&self, fd: FileDesc) -> Result<()> {
+        if fd == -1 {
+            return self.event.set_output(None);
+        }
+
+        let file = {
+            let task = Task::current().unwrap();
+            let thread_local = task.as_thread_local().unwrap();
+            let file_table = thread_local.borrow_file_table();
+            let file_table_locked = file_table.unwrap().read();
+            file_table_locked.get_file(fd)?.clone()
+        };
+        let Some(target_file) = file.downcast_ref::<PerfEventFile>() else {
+            return_errno_with_message!(Errno::EINVAL, "the file is not a perf event file");
+        };
+        if target_file.event.cpu() != self.event.cpu() {
+            return_errno_with_message!(Errno::EINVAL, "the events are on different CPUs");
+        }
+        self.event.set_output(Some(&target_file.event))
+    }
+}
+
+/// Adds the counts of the hardware counters on the current CPU to their events.
+///
+/// The counts are otherwise added only at timer interrupts and context switches, so an event that
+/// counts the current thread may have an outdated count.
+fn sync_hw_counters() {
+    hw::sync_counters(&disable_local());
+}
+
+impl Pollable for PerfEventFile {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.event.poll(mask, poller)
+    }
+}
+
+impl FileLike for PerfEventFile {
+    fn read(&self, writer: &mut VmWriter) -> Result<usize> {
+        sync_hw_counters();
+        self.event.read(writer)
+    }
+
+    fn ioctl(&self, raw_ioctl: RawIoctl) -> Result<i32> {
+        use ioctl_defs::*;
+
+        // The events that are enabled, disabled, or reset should have the latest counts.
+        sync_hw_counters();
+
+        dispatch_ioctl!(match raw_ioctl {
+            cmd @ Enable => {
+                let is_group = cmd.get() & PERF_IOC_FLAG_GROUP != 0;
+                self.event.set_enabled(true, is_group);
+            }
+            cmd @ Disable => {
+                let is_group = cmd.get() & PERF_IOC_FLAG_GROUP != 0;
+                self.event.set_enabled(false, is_group);
+            }
+            cmd @ Reset => {
+                let is_group = cmd.get() & PERF_IOC_FLAG_GROUP != 0;
+                self.event.reset(is_group);
+            }
+            cmd @ Period => {
+                self.event.set_sample_period(cmd.read()?)?;
+            }
+            cmd @ SetOutput => {
+                self.set_output(cmd.get())?;
+            }
+            cmd @ Id => {
+                cmd.write(&self.event.id())?;
+            }
+            _ => return_errno_with_message!(
+                Errno::ENOTTY,
+                "the ioctl command is not supported by perf event files"
+            ),
+        });
+
+        Ok(0)
+    }
+
+    fn mappable(&self) -> Result<Mappable> {
+        match self.event.ring_buffer() {
+            Some(ring_buffer) => Ok(Mappable::Vmo(ring_buffer.vmo().clone())),
+            None => return_errno_with_message!(Errno::ENODEV, "the ring buffer is not set up"),
+        }
+    }
+
+    fn path(&self) -> &Path {
+        &self.pseudo_path
+    }
+
+    fn dump_proc_fdinfo(self: Arc<Self>, fd_flags: FdFlags) -> Box<dyn Display> {
+        struct FdInfo {
+            inner: Arc<PerfEventFile>,
+            fd_flags: FdFlags,
+        }
+
+        impl Display for FdInfo {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                let mut flags = self.inner.status_flags().bits() | self.inner.access_mode() as u32;
+                if self.fd_flags.contains(FdFlags::CLOEXEC) {
+                    flags |= CreationFlags::O_CLOEXEC.bits();
+                }
+
+                writeln!(f, "pos:\t{}", 0)?;
+                writeln!(f, "flags:\t0{:o}", flags)?;
+                writeln!(f, "mnt_id:\t{}", AnonInodeFs::mount_node().id())?;
+                writeln!(f, "ino:\t{}", AnonInodeFs::shared_inode().ino())
+            }
+        }
+
+        Box::new(FdInfo {
+            inner: self,
+            fd_flags,
+        })
+    }
+}
+
+mod ioctl_defs {
+    use crate::util::ioctl::{InData, OutData, PassByVal, ioc};
+
+    // Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/perf_event.h#L561>
+
+    pub(super) type Enable    = ioc!(PERF_EVENT_IOC_ENABLE,     0x2400,     InData<u32, PassByVal>);
+    pub(super) type Disable   = ioc!(PERF_EVENT_IOC_DISABLE,    0x2401,     InData<u32, PassByVal>);
+    pub(super) type Reset     = ioc!(PERF_EVENT_IOC_RESET,      0x2403,     InData<u32, PassByVal>);
+    pub(super) type Period    = ioc!(PERF_EVENT_IOC_PERIOD,     b'$', 4,    InData<u64>);
+    pub(super) type SetOutput = ioc!(PERF_EVENT_IOC_SET_OUTPUT, 0x2405,     InData<i32, PassByVal>);
+    pub(super) type Id        = ioc!(PERF_EVENT_IOC_ID,         b'$', 7,    OutData<u64>);
+}
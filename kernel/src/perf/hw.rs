@@ -0,0 +1,107 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The hardware counters that count the hardware events on each CPU.
+
+use core::cell::RefCell;
+
+use ostd::{cpu_local, irq::DisabledLocalIrqGuard};
+
+use super::{
+    attr::{PerfEventFlags, PerfEventKind},
+    event::PerfEvent,
+    now_ns,
+};
+use crate::{arch::pmu::HwCounter, prelude::*};
+
+cpu_local! {
+    /// The hardware counters that are counting on this CPU.
+    static ACTIVE_COUNTERS: RefCell<Vec<ActiveCounter>> = RefCell::new(Vec::new());
+}
+
+struct ActiveCounter {
+    event: Weak<PerfEvent>,
+    counter: HwCounter,
+    /// Whether the event is attached to this CPU, rather than to the thread running on it.
+    is_per_cpu: bool,
+}
+
+/// Starts counting the hardware event with a hardware counter on the current CPU.
+///
+/// This function does nothing if the event is not a hardware event or is already counting on the
+/// current CPU. If there are no free hardware counters, the event is scheduled but not counting,
+/// which is reflected in its running time.
+pub(super) fn start_counter(
+    event: &Arc<PerfEvent>,
+    is_per_cpu: bool,
+    irq_guard: &DisabledLocalIrqGuard,
+) {
+    let PerfEventKind::Hardware(hw_event) = event.attr().kind else {
+        return;
+    };
+
+    let mut active_counters = ACTIVE_COUNTERS.get_with(irq_guard).borrow_mut();
+    if active_counters
+        .iter()
+        .any(|active| core::ptr::eq(active.event.as_ptr(), Arc::as_ptr(event)))
+    {
+        return;
+    }
+
+    let flags = event.attr().flags;
+    let Some(counter) = HwCounter::start(
+        hw_event,
+        !flags.contains(PerfEventFlags::EXCLUDE_USER),
+        !flags.contains(PerfEventFlags::EXCLUDE_KERNEL),
+        irq_guard,
+    ) else {
+        return;
+    };
+
+    event.mark_counter_started(now_ns());
+    active_counters.push(ActiveCounter {
+        event: Arc::downgrade(event),
+        counter,
+        is_per_cpu,
+    });
+}
+
+/// Adds the counts of the hardware counters on the current CPU to their events.
+///
+/// The hardware counters of the events that no longer exist are stopped.
+pub(super) fn sync_counters(irq_guard: &DisabledLocalIrqGuard) {
+    ACTIVE_COUNTERS
+        .get_with(irq_guard)
+        .borrow_mut()
+        .retain_mut(|active| {
+            let Some(event) = active.event.upgrade() else {
+                return false;
+            };
+            add_count(&event, active.counter.take_count(irq_guard));
+            true
+        });
+}
+
+/// Stops the hardware counters on the current CPU whose events are attached to the current
+/// thread.
+pub(super) fn stop_thread_counters(irq_guard: &DisabledLocalIrqGuard) {
+    let now = now_ns();
+    ACTIVE_COUNTERS
+        .get_with(irq_guard)
+        .borrow_mut()
+        .retain_mut(|active| {
+            if active.is_per_cpu {
+                return true;
+            }
+            if let Some(event) = active.event.upgrade() {
+                add_count(&event, active.counter.take_count(irq_guard));
+                event.mark_counter_stopped(now);
+            }
+            false
+        });
+}
+
+fn add_count(event: &PerfEvent, count: u64) {
+    // Hardware events cannot be sampled, so no samples will be taken.
+    let sample_period = event.add_hw_count(count);
+    debug_assert!(sample_period.is_none());
+}
@@ -0,0 +1,213 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Perf events, which are created by the `perf_event_open` system call.
+//!
+//! A perf event counts the occurrences of a hardware event (e.g., CPU cycles), which is counted by
+//! a hardware counter of the PMU, or a software event (e.g., context switches), which is counted
+//! by the kernel. An event is attached either to a thread or to a CPU. A thread event counts only
+//! when the thread is running, and a CPU event counts whatever runs on the CPU.
+//!
+//! A sampled event takes a sample every time a number of events happen. The samples are written to
+//! the ring buffer that is mapped by user space. Since the user-space context is needed for the
+//! samples, they are queued in the current thread and written when the thread returns to user
+//! space.
+//!
+//! Unlike Linux, the hardware counters do not raise interrupts on overflows. Their counts are
+//! collected at timer interrupts and context switches, so hardware events cannot be sampled.
+//!
+//! Reference: <https://man7.org/linux/man-pages/man2/perf_event_open.2.html>.
+
+mod attr;
+mod context;
+mod event;
+mod file;
+mod hw;
+mod ring_buffer;
+
+use ostd::{
+    arch::cpu::context::UserContext,
+    cpu::{CpuId, PinCurrentCpu, PrivilegeLevel},
+    cpu_local,
+    irq::{InterruptLevel, disable_local},
+    sync::LocalIrqDisabled,
+    task::Task,
+    user::UserContextApi,
+};
+
+pub(crate) use self::{
+    attr::{CPerfEventAttr, HwEvent, PERF_ATTR_SIZE_VER0, PerfEventAttr},
+    context::PerfEventContext,
+    event::PerfEvent,
+    file::PerfEventFile,
+};
+use self::{attr::SwEvent, event::PendingSample};
+use crate::{prelude::*, process::posix_thread::AsPosixThread};
+
+cpu_local! {
+    /// The events that are attached to this CPU.
+    static CPU_EVENTS: SpinLock<Vec<Weak<PerfEvent>>, LocalIrqDisabled> = SpinLock::new(Vec::new());
+}
+
+pub(super) fn init_on_each_cpu() {
+    ostd::timer::register_callback_on_cpu(handle_timer_tick);
+}
+
+/// Returns the current time in nanoseconds, which is used to measure the times of the events.
+fn now_ns() -> u64 {
+    aster_time::read_monotonic_time().as_nanos() as u64
+}
+
+/// Attaches the event to the CPU.
+pub(crate) fn attach_to_cpu(event: &Arc<PerfEvent>, cpu: CpuId) {
+    CPU_EVENTS.get_on_cpu(cpu).lock().push(Arc::downgrade(event));
+}
+
+/// Calls the function for each event that is attached to the CPU.
+fn for_each_cpu_event(cpu: CpuId, mut f: impl FnMut(&Arc<PerfEvent>)) {
+    let mut cpu_events = CPU_EVENTS.get_on_cpu(cpu).lock();
+    cpu_events.retain(|event| event.strong_count() > 0);
+    cpu_events.iter().filter_map(Weak::upgrade).for_each(|event| f(&event));
+}
+
+/// Counts the software event that happens on the current thread and the current CPU.
+///
+/// If the event is related to an address (e.g., the faulting address of page faults), `addr`
+/// should be the address. Otherwise, `addr` should be zero.
+fn count_sw_event(sw_event: SwEvent, count: u64, is_user: bool, addr: Vaddr) {
+    let irq_guard = disable_local();
+    let cpu = irq_guard.current_cpu();
+    let current_task = Task::current();
+    let context = current_task
+        .as_ref()
+        .and_then(|task| task.as_posix_thread())
+        .map(|posix_thread| posix_thread.perf_events());
+
+    let mut samples = Vec::new();
+    let mut count_event = |event: &Arc<PerfEvent>| {
+        if !event.is_software(sw_event) {
+            return;
+        }
+        if let Some(period) = event.add_count(count, is_user) {
+            samples.push(PendingSample {
+                event: event.clone(),
+                period,
+                addr,
+                time: now_ns(),
+                cpu,
+            });
+        }
+    };
+    if let Some(context) = context {
+        context.for_each_scheduled(&mut count_event);
+    }
+    for_each_cpu_event(cpu, &mut count_event);
+
+    queue_samples(context, samples);
+}
+
+/// Queues the samples to the current thread.
+fn queue_samples(context: Option<&PerfEventContext>, samples: Vec<PendingSample>) {
+    if samples.is_empty() {
+        return;
+    }
+
+    // TODO: Support taking samples in kernel threads. For now, samples are taken only in user
+    // space, so the samples in kernel threads are discarded.
+    if let Some(context) = context {
+        context.queue_samples(samples);
+    }
+}
+
+/// Counts the page fault at the address.
+pub(crate) fn count_page_fault(addr: Vaddr, is_user: bool) {
+    count_sw_event(SwEvent::PageFaults, 1, is_user, addr);
+    // TODO: Distinguish major page faults, which require I/O, from minor page faults. For now,
+    // all page faults are counted as minor page faults.
+    count_sw_event(SwEvent::PageFaultsMin, 1, is_user, addr);
+}
+
+/// Switches out the events of the current task before the task is switched out.
+pub(crate) fn switch_out(current_task: &Task) {
+    count_sw_event(SwEvent::ContextSwitches, 1, false, 0);
+
+    let irq_guard = disable_local();
+    hw::stop_thread_counters(&irq_guard);
+    if let Some(posix_thread) = current_task.as_posix_thread() {
+        posix_thread.perf_events().switch_out(now_ns());
+    }
+}
+
+/// Switches in the events of the current task after the task is switched in.
+pub(crate) fn switch_in(current_task: &Task) {
+    let Some(posix_thread) = current_task.as_posix_thread() else {
+        return;
+    };
+
+    let is_migrated = {
+        let irq_guard = disable_local();
+        posix_thread.perf_events().switch_in(now_ns(), &irq_guard)
+    };
+    if is_migrated {
+        count_sw_event(SwEvent::CpuMigrations, 1, false, 0);
+    }
+}
+
+fn handle_timer_tick() {
+    let InterruptLevel::L1(cpu_priv_at_irq) = InterruptLevel::current() else {
+        // The bottom half of IRQ handling is interrupted, so the interrupted code is unknown.
+        return;
+    };
+    let is_user = cpu_priv_at_irq == PrivilegeLevel::User;
+
+    let irq_guard = disable_local();
+    let cpu = irq_guard.current_cpu();
+    let now = now_ns();
+    let current_task = Task::current();
+    let context = current_task
+        .as_ref()
+        .and_then(|task| task.as_posix_thread())
+        .map(|posix_thread| posix_thread.perf_events());
+
+    hw::sync_counters(&irq_guard);
+
+    let mut samples = Vec::new();
+    let mut tick_event = |event: &Arc<PerfEvent>, is_per_cpu: bool| {
+        if event.is_clock() {
+            if let Some(period) = event.tick_clock(now, is_user) {
+                samples.push(PendingSample {
+                    event: event.clone(),
+                    period,
+                    addr: 0,
+                    time: now,
+                    cpu,
+                });
+            }
+        } else {
+            // Retry starting the hardware counters if there were no free hardware counters.
+            hw::start_counter(event, is_per_cpu, &irq_guard);
+        }
+    };
+    if let Some(context) = context {
+        // The events attached when the thread is running on this CPU are scheduled now.
+        context.schedule(now, &irq_guard);
+        context.for_each_scheduled(|event| tick_event(event, false));
+    }
+    for_each_cpu_event(cpu, |event| tick_event(event, true));
+
+    queue_samples(context, samples);
+}
+
+/// Writes the pending samples of the current thread to the ring buffers.
+pub(crate) fn handle_pending_samples(ctx: &Context, user_ctx: &UserContext) {
+    let context = ctx.posix_thread.perf_events();
+    if !context.has_pending_samples() {
+        return;
+    }
+
+    let ip = user_ctx.instruction_pointer();
+    let pid = ctx.process.pid();
+    let tid = ctx.posix_thread.tid();
+    for sample in context.take_pending_samples() {
+        sample.event.output_sample(&sample, ip, pid, tid);
+    }
+}
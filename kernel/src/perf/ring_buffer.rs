@@ -0,0 +1,192 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{Ordering, fence};
+
+use ostd::mm::VmIo;
+
+use crate::{
+    events::IoEvents,
+    prelude::*,
+    process::signal::{PollHandle, Pollee},
+    vm::vmo::{CommitFlags, Vmo, VmoOptions},
+};
+
+/// The ring buffer of a perf event, which delivers the records to user space.
+///
+/// The ring buffer is mapped by user space. The first page is the header page, which contains the
+/// head position written by the kernel and the tail position written by user space. The
+/// following pages, whose number is a power of two, are the data area, where the records are
+/// written at the head and consumed at the tail.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/perf_event.h#L563>.
+pub struct RingBuffer {
+    vmo: Arc<Vmo>,
+    data_size: usize,
+    wakeup: Wakeup,
+    state: SpinLock<RingBufferState>,
+    pollee: Pollee,
+}
+
+struct RingBufferState {
+    /// The head position, which is also written to the header page.
+    head: u64,
+    /// The number of the records written since user space is last woken up.
+    nr_unwoken_records: u32,
+}
+
+/// When user space is woken up to consume the records.
+#[derive(Debug, Clone, Copy)]
+pub enum Wakeup {
+    /// User space is woken up every the number of records.
+    Records(u32),
+    /// User space is woken up if the unconsumed records have at least the number of bytes.
+    Bytes(u64),
+}
+
+/// The offsets of the fields in the header page (`struct perf_event_mmap_page`).
+const TIME_ENABLED_OFFSET: usize = 24;
+const TIME_RUNNING_OFFSET: usize = 32;
+const CAPABILITIES_OFFSET: usize = 40;
+const DATA_HEAD_OFFSET: usize = 1024;
+const DATA_TAIL_OFFSET: usize = 1032;
+const DATA_OFFSET_OFFSET: usize = 1040;
+const DATA_SIZE_OFFSET: usize = 1048;
+
+/// The capability indicating that the deprecated capability bit 0 should be ignored.
+const CAP_BIT0_IS_DEPRECATED: u64 = 1 << 1;
+
+/// The maximum size of the data area.
+const MAX_DATA_SIZE: usize = 64 * 1024 * 1024;
+
+impl RingBuffer {
+    /// Creates a ring buffer that is mapped with the size.
+    ///
+    /// The size includes the header page and must be one page plus a power of two pages.
+    pub(super) fn new(size: usize, wakeup: Wakeup) -> Result<Self> {
+        if size % PAGE_SIZE != 0 || size <= PAGE_SIZE {
+            return_errno_with_message!(Errno::EINVAL, "the ring buffer size is invalid");
+        }
+        let data_size = size - PAGE_SIZE;
+        if !(data_size / PAGE_SIZE).is_power_of_two() {
+            return_errno_with_message!(Errno::EINVAL, "the data area size is not a power of two");
+        }
+        if data_size > MAX_DATA_SIZE {
+            return_errno_with_message!(Errno::ENOMEM, "the ring buffer is too large");
+        }
+
+        // Records are written when the samples are taken, where we do not want to fail because
+        // of committing pages. So all the pages are committed in advance.
+        let vmo = VmoOptions::new(size).alloc()?;
+        for page_idx in 0..size / PAGE_SIZE {
+            vmo.commit_on(page_idx, CommitFlags::empty())?;
+        }
+
+        let ring_buffer = Self {
+            vmo,
+            data_size,
+            wakeup,
+            state: SpinLock::new(RingBufferState {
+                head: 0,
+                nr_unwoken_records: 0,
+            }),
+            pollee: Pollee::new(),
+        };
+        ring_buffer.write_val(CAPABILITIES_OFFSET, &CAP_BIT0_IS_DEPRECATED);
+        ring_buffer.write_val(DATA_OFFSET_OFFSET, &(PAGE_SIZE as u64));
+        ring_buffer.write_val(DATA_SIZE_OFFSET, &(data_size as u64));
+
+        Ok(ring_buffer)
+    }
+
+    /// Returns the VMO to be mapped by user space.
+    pub(super) fn vmo(&self) -> &Arc<Vmo> {
+        &self.vmo
+    }
+
+    /// Returns the size of the mapping, including the header page.
+    pub(super) fn size(&self) -> usize {
+        PAGE_SIZE + self.data_size
+    }
+
+    /// Writes a record, whose length must be a multiple of eight bytes.
+    ///
+    /// This method returns `false` if there is not enough space.
+    pub(super) fn write_record(&self, record: &[u8]) -> bool {
+        debug_assert_eq!(record.len() % 8, 0);
+
+        let mut state = self.state.lock();
+
+        let tail = self.read_val::<u64>(DATA_TAIL_OFFSET);
+        fence(Ordering::Acquire);
+        let new_head = state.head + record.len() as u64;
+        if new_head.wrapping_sub(tail) > self.data_size as u64 {
+            return false;
+        }
+
+        let offset = (state.head % self.data_size as u64) as usize;
+        let first_len = record.len().min(self.data_size - offset);
+        self.write_bytes(PAGE_SIZE + offset, &record[..first_len]);
+        self.write_bytes(PAGE_SIZE, &record[first_len..]);
+
+        // The record must be visible before the head position.
+        fence(Ordering::Release);
+        self.write_val(DATA_HEAD_OFFSET, &new_head);
+        state.head = new_head;
+
+        let should_wake = match self.wakeup {
+            Wakeup::Records(nr_records) => {
+                state.nr_unwoken_records += 1;
+                if state.nr_unwoken_records >= nr_records {
+                    state.nr_unwoken_records = 0;
+                    true
+                } else {
+                    false
+                }
+            }
+            Wakeup::Bytes(nr_bytes) => new_head.wrapping_sub(tail) >= nr_bytes,
+        };
+        drop(state);
+
+        if should_wake {
+            self.pollee.notify(IoEvents::IN);
+        }
+        true
+    }
+
+    /// Updates the times of the event in the header page.
+    pub(super) fn update_times(&self, time_enabled: u64, time_running: u64) {
+        self.write_val(TIME_ENABLED_OFFSET, &time_enabled);
+        self.write_val(TIME_RUNNING_OFFSET, &time_running);
+    }
+
+    /// Polls the ring buffer, which is readable if there are records not consumed.
+    pub(super) fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        // User space consumes the records without notifying us, so the cached events may be
+        // outdated.
+        self.pollee.invalidate();
+        self.pollee.poll_with(mask, poller, || {
+            let tail = self.read_val::<u64>(DATA_TAIL_OFFSET);
+            let head = self.read_val::<u64>(DATA_HEAD_OFFSET);
+            if tail != head {
+                IoEvents::IN
+            } else {
+                IoEvents::empty()
+            }
+        })
+    }
+
+    fn read_val<T: Pod>(&self, offset: usize) -> T {
+        // The pages are committed, so reading them never fails.
+        self.vmo.read_val(offset).unwrap()
+    }
+
+    fn write_val<T: Pod>(&self, offset: usize, val: &T) {
+        // The pages are committed, so writing them never fails.
+        self.vmo.write_val(offset, val).unwrap();
+    }
+
+    fn write_bytes(&self, offset: usize, bytes: &[u8]) {
+        // The pages are committed, so writing them never fails.
+        self.vmo.write_bytes(offset, bytes).unwrap();
+    }
+}
@@ -0,0 +1,60 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The freezer that stops user tasks before the system sleeps.
+//!
+//! When freezing, user tasks are forced to return from the user space, and they will be blocked
+//! at the freeze point (see [`try_to_freeze`]) until the system is thawed. User tasks that are
+//! sleeping in the kernel will be blocked at the freeze point once they are about to return to
+//! the user space.
+//!
+//! Kernel threads are never frozen.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use ostd::sync::WaitQueue;
+
+use crate::thread::Thread;
+
+static FREEZING: AtomicBool = AtomicBool::new(false);
+static THAW_WAIT_QUEUE: WaitQueue = WaitQueue::new();
+
+/// The number of times to yield the CPU so that the runnable user tasks can reach the freeze
+/// point.
+const FREEZE_YIELD_ROUNDS: usize = 16;
+
+/// Returns whether the user tasks should be frozen.
+pub fn is_freezing() -> bool {
+    FREEZING.load(Ordering::Acquire)
+}
+
+/// Blocks the current user task if the user tasks are being frozen.
+///
+/// This is the freeze point. It should be called before the user task returns to the user space.
+pub fn try_to_freeze() {
+    if !is_freezing() {
+        return;
+    }
+
+    THAW_WAIT_QUEUE.wait_until(|| (!is_freezing()).then_some(()));
+}
+
+/// Freezes the user tasks.
+///
+/// This function returns after the runnable user tasks have had a chance to reach the freeze
+/// point.
+//
+// TODO: Wait until all user tasks are actually frozen, and fail if some of them cannot be frozen
+// in time, like Linux does.
+pub(super) fn freeze_user_tasks() {
+    FREEZING.store(true, Ordering::Release);
+
+    for _ in 0..FREEZE_YIELD_ROUNDS {
+        Thread::yield_now();
+    }
+}
+
+/// Thaws the frozen user tasks.
+pub(super) fn thaw_user_tasks() {
+    FREEZING.store(false, Ordering::Release);
+    THAW_WAIT_QUEUE.wake_all();
+}
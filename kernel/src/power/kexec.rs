@@ -0,0 +1,98 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Rebooting into a new kernel (kexec).
+//!
+//! A new kernel image is loaded in advance by `kexec_load` or `kexec_file_load`, and it is
+//! executed by `reboot(LINUX_REBOOT_CMD_KEXEC)` without going through the firmware.
+//!
+//! A crash kernel can also be loaded into the memory reserved by the `crashkernel=` kernel
+//! parameter. It is executed when the kernel panics.
+//!
+//! Reference: <https://man7.org/linux/man-pages/man2/kexec_load.2.html>
+
+use core::convert::Infallible;
+
+use ostd::power::KexecImage;
+use spin::Once;
+
+use super::{SLEEP_LOCK, freezer};
+use crate::prelude::*;
+
+/// The loaded kernel image to be executed on reboot.
+static KEXEC_IMAGE: Mutex<Option<KexecImage>> = Mutex::new(None);
+
+/// The lock that serializes loading the crash kernel image.
+static CRASH_LOAD_LOCK: Mutex<()> = Mutex::new(());
+
+/// The memory reserved for the crash kernel.
+///
+/// The memory is reserved by OSTD when initializing the frame allocator. The parameter is
+/// registered here so that it is not passed to the init process.
+static CRASH_KERNEL: Once<String> = Once::new();
+aster_cmdline::define_kv_param!("crashkernel", CRASH_KERNEL);
+
+pub(super) fn init() {
+    if CRASH_KERNEL.get().is_some() && ostd::power::crash_kernel_region().is_none() {
+        warn!("[power] no memory is reserved for the crash kernel");
+    }
+}
+
+/// Loads a new kernel image, replacing the previously loaded one.
+pub fn load_image(image: KexecImage) {
+    *KEXEC_IMAGE.lock() = Some(image);
+}
+
+/// Unloads the loaded kernel image, if any.
+pub fn unload_image() {
+    *KEXEC_IMAGE.lock() = None;
+}
+
+/// Returns whether a kernel image is loaded.
+pub fn is_image_loaded() -> bool {
+    KEXEC_IMAGE.lock().is_some()
+}
+
+/// Loads a crash kernel image, replacing the previously loaded one.
+///
+/// The previous image is unloaded before `new_image` is called, since the images are placed
+/// directly in the memory reserved for the crash kernel.
+pub fn load_crash_image(new_image: impl FnOnce() -> Result<KexecImage>) -> Result<()> {
+    let _guard = CRASH_LOAD_LOCK.lock();
+
+    ostd::power::unload_crash_image();
+    let image = new_image()?;
+    ostd::power::load_crash_image(image)?;
+
+    Ok(())
+}
+
+/// Unloads the loaded crash kernel image, if any.
+pub fn unload_crash_image() {
+    let _guard = CRASH_LOAD_LOCK.lock();
+
+    ostd::power::unload_crash_image();
+}
+
+/// Reboots into the loaded kernel image.
+///
+/// This function does not return if it succeeds.
+pub fn kexec() -> Result<Infallible> {
+    let Some(_guard) = SLEEP_LOCK.try_lock() else {
+        return_errno_with_message!(Errno::EBUSY, "the system is suspending");
+    };
+
+    let image = KEXEC_IMAGE.lock();
+    let Some(image) = image.as_ref() else {
+        return_errno_with_message!(Errno::EINVAL, "no kernel image is loaded");
+    };
+
+    info!("[power] rebooting into the new kernel");
+
+    freezer::freeze_user_tasks();
+    let Err(err) = ostd::power::kexec(image);
+    freezer::thaw_user_tasks();
+
+    warn!("[power] failed to reboot into the new kernel: {:?}", err);
+
+    Err(err.into())
+}
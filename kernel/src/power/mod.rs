@@ -0,0 +1,89 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! System sleep support.
+//!
+//! The sleeping state can be requested by writing to `/sys/power/state`. Currently, only
+//! suspend-to-RAM (`mem`) is supported.
+//!
+//! Rebooting into a new kernel (kexec) is also supported (see [`kexec`]), including switching to
+//! the crash kernel on panic and dumping the memory of the crashed kernel (see [`vmcore`]).
+//!
+//! Reference: <https://docs.kernel.org/admin-guide/pm/sleep-states.html>
+
+mod freezer;
+pub mod kexec;
+pub mod vmcore;
+
+pub use freezer::{is_freezing, try_to_freeze};
+
+use crate::{
+    fs::sysfs::{self, KObject, ShowFn, StoreFn},
+    prelude::*,
+};
+
+/// The lock that serializes the system sleep transitions, including rebooting into a new kernel.
+static SLEEP_LOCK: Mutex<()> = Mutex::new(());
+
+pub(super) fn init_in_first_kthread() {
+    kexec::init();
+    vmcore::init();
+
+    sysfs::systree_singleton()
+        .root()
+        .add_child(new_power_kobject())
+        .unwrap();
+}
+
+fn new_power_kobject() -> Arc<KObject> {
+    let show_state = Box::new(|| {
+        if ostd::power::can_suspend() {
+            "mem\n".to_string()
+        } else {
+            String::new()
+        }
+    }) as ShowFn;
+
+    let store_state = Box::new(|state: &str| match state.trim() {
+        "mem" => suspend_to_ram().map_err(|err| match err.error() {
+            Errno::EINVAL => aster_systree::Error::InvalidOperation,
+            Errno::EBUSY => aster_systree::Error::ResourceUnavailable,
+            _ => aster_systree::Error::InternalError("failed to suspend the system"),
+        }),
+        _ => Err(aster_systree::Error::InvalidOperation),
+    }) as StoreFn;
+
+    KObject::new_with_stores(
+        "power",
+        vec![("state", show_state)],
+        vec![("state", store_state)],
+    )
+}
+
+/// Suspends the system to RAM.
+///
+/// This function returns after the system wakes up.
+fn suspend_to_ram() -> Result<()> {
+    if !ostd::power::can_suspend() {
+        return_errno_with_message!(Errno::EINVAL, "suspend-to-RAM is not supported");
+    }
+
+    let Some(_guard) = SLEEP_LOCK.try_lock() else {
+        return_errno_with_message!(Errno::EBUSY, "the system is already suspending");
+    };
+
+    info!("[power] suspending the system to RAM");
+
+    freezer::freeze_user_tasks();
+    let res = ostd::power::suspend();
+    freezer::thaw_user_tasks();
+
+    // TODO: Resynchronize the realtime clock with the RTC, since the time spent in the sleeping
+    // state is not counted by the clock sources.
+
+    match res {
+        Ok(()) => info!("[power] the system has woken up"),
+        Err(err) => warn!("[power] failed to suspend the system: {:?}", err),
+    }
+
+    res.map_err(Error::from)
+}
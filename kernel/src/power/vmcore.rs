@@ -0,0 +1,302 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The memory dump of the crashed kernel (kdump).
+//!
+//! When the crash kernel is loaded, an ELF core header describing the memory of the current
+//! kernel is passed to the crash kernel with the `elfcorehdr=` kernel parameter. After the
+//! current kernel panics and the crash kernel boots, the crash kernel exposes the memory of the
+//! crashed kernel as an ELF core file at `/proc/vmcore`.
+//!
+//! Reference: <https://docs.kernel.org/admin-guide/kdump/kdump.html>
+
+use core::ops::Range;
+
+use align_ext::AlignExt;
+use ostd::mm::Paddr;
+use spin::Once;
+
+use crate::prelude::*;
+
+/// Creates an ELF core header that describes the memory ranges.
+///
+/// Each range is described by a `PT_LOAD` program header, whose file offset is the physical
+/// address of the range, as expected by [`Vmcore`].
+#[cfg_attr(not(target_arch = "x86_64"), expect(dead_code))]
+pub fn new_elf_core_header(ranges: &[Range<Paddr>]) -> Vec<u8> {
+    let phdrs = ranges
+        .iter()
+        .map(|range| Elf64Phdr {
+            p_type: PT_LOAD,
+            p_flags: PF_RWX,
+            p_offset: range.start as u64,
+            p_vaddr: 0,
+            p_paddr: range.start as u64,
+            p_filesz: range.len() as u64,
+            p_memsz: range.len() as u64,
+            p_align: 0,
+        })
+        .collect::<Vec<_>>();
+
+    new_elf_header_bytes(&phdrs)
+}
+
+/// Returns the size of the ELF core header created by [`new_elf_core_header`].
+pub fn elf_core_header_size(nr_ranges: usize) -> usize {
+    size_of::<Elf64Ehdr>() + nr_ranges * size_of::<Elf64Phdr>()
+}
+
+fn new_elf_header_bytes(phdrs: &[Elf64Phdr]) -> Vec<u8> {
+    let mut e_ident = [0u8; 16];
+    e_ident[..4].copy_from_slice(ELF_MAGIC);
+    e_ident[EI_CLASS] = ELFCLASS64;
+    e_ident[EI_DATA] = ELFDATA2LSB;
+    e_ident[EI_VERSION] = EV_CURRENT;
+
+    let ehdr = Elf64Ehdr {
+        e_ident,
+        e_type: ET_CORE,
+        e_machine: (crate::arch::kexec::KEXEC_ARCH >> 16) as u16,
+        e_version: EV_CURRENT as u32,
+        e_entry: 0,
+        e_phoff: size_of::<Elf64Ehdr>() as u64,
+        e_shoff: 0,
+        e_flags: 0,
+        e_ehsize: size_of::<Elf64Ehdr>() as u16,
+        e_phentsize: size_of::<Elf64Phdr>() as u16,
+        e_phnum: phdrs.len() as u16,
+        e_shentsize: 0,
+        e_shnum: 0,
+        e_shstrndx: 0,
+    };
+
+    let mut bytes = Vec::with_capacity(elf_core_header_size(phdrs.len()));
+    bytes.extend_from_slice(ehdr.as_bytes());
+    for phdr in phdrs.iter() {
+        bytes.extend_from_slice(phdr.as_bytes());
+    }
+    bytes
+}
+
+/// The memory dump of the crashed kernel.
+pub struct Vmcore {
+    /// The ELF core header, which is padded to the file offset of the first segment.
+    header: Vec<u8>,
+    /// The segments of the memory of the crashed kernel.
+    segments: Vec<VmcoreSegment>,
+    /// The total size of the ELF core file.
+    size: usize,
+}
+
+struct VmcoreSegment {
+    /// The file offset of the segment.
+    offset: usize,
+    /// The physical memory of the segment.
+    range: Range<Paddr>,
+}
+
+static VMCORE: Once<Option<Vmcore>> = Once::new();
+
+/// The address of the ELF core header passed by the crashed kernel.
+static ELFCOREHDR: Once<String> = Once::new();
+aster_cmdline::define_kv_param!("elfcorehdr", ELFCOREHDR);
+
+/// Returns the memory dump of the crashed kernel, if the current kernel is a crash kernel.
+pub fn vmcore() -> Option<&'static Vmcore> {
+    VMCORE.get().and_then(Option::as_ref)
+}
+
+pub(super) fn init() {
+    VMCORE.call_once(|| {
+        let arg = ELFCOREHDR.get()?;
+        match Vmcore::new(arg) {
+            Ok(vmcore) => {
+                info!("[kdump] the memory dump of the crashed kernel is available");
+                Some(vmcore)
+            }
+            Err(err) => {
+                warn!("[kdump] failed to read the ELF core header: {:?}", err);
+                None
+            }
+        }
+    });
+}
+
+impl Vmcore {
+    /// The maximum number of program headers in the ELF core header.
+    const MAX_PHNUM: usize = 1024;
+
+    fn new(elfcorehdr: &str) -> Result<Self> {
+        let header_addr = parse_elfcorehdr(elfcorehdr)
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "the address is invalid"))?;
+
+        let mut ehdr = Elf64Ehdr::new_zeroed();
+        read_old_memory_exact(header_addr, ehdr.as_mut_bytes())?;
+        if &ehdr.e_ident[..4] != ELF_MAGIC
+            || ehdr.e_ident[EI_CLASS] != ELFCLASS64
+            || ehdr.e_ident[EI_DATA] != ELFDATA2LSB
+            || ehdr.e_type != ET_CORE
+            || ehdr.e_phentsize as usize != size_of::<Elf64Phdr>()
+            || ehdr.e_phnum as usize > Self::MAX_PHNUM
+        {
+            return_errno_with_message!(Errno::EINVAL, "the ELF core header is invalid");
+        }
+
+        let mut old_phdrs = vec![Elf64Phdr::new_zeroed(); ehdr.e_phnum as usize];
+        let phdrs_addr = header_addr
+            .checked_add(ehdr.e_phoff as usize)
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "the program headers overflow"))?;
+        read_old_memory_exact(phdrs_addr, old_phdrs.as_mut_slice().as_mut_bytes())?;
+
+        // TODO: Merge the `PT_NOTE` segments, which contain the registers of the CPUs when the
+        // kernel crashed. They are not generated by the crashed kernel yet.
+        let old_phdrs = old_phdrs
+            .into_iter()
+            .filter(|phdr| phdr.p_type == PT_LOAD && phdr.p_filesz != 0)
+            .collect::<Vec<_>>();
+
+        // Rewrite the file offsets from the physical addresses to the offsets in the file. Like
+        // Linux, the segments are page-aligned in the file so that they can be mapped.
+        let mut offset = elf_core_header_size(old_phdrs.len()).align_up(PAGE_SIZE);
+        let mut phdrs = Vec::with_capacity(old_phdrs.len());
+        let mut segments = Vec::with_capacity(old_phdrs.len());
+        for old_phdr in old_phdrs.iter() {
+            let start = old_phdr.p_offset as Paddr;
+            let end = start
+                .checked_add(old_phdr.p_filesz as usize)
+                .ok_or_else(|| Error::with_message(Errno::EINVAL, "the segment overflows"))?;
+
+            phdrs.push(Elf64Phdr {
+                p_offset: offset as u64,
+                ..*old_phdr
+            });
+            segments.push(VmcoreSegment {
+                offset,
+                range: start..end,
+            });
+            offset = (offset + (end - start)).align_up(PAGE_SIZE);
+        }
+
+        let mut header = new_elf_header_bytes(&phdrs);
+        header.resize(
+            segments
+                .first()
+                .map_or(header.len(), |segment| segment.offset),
+            0,
+        );
+        let size = segments
+            .last()
+            .map_or(header.len(), |segment| segment.offset + segment.range.len());
+
+        Ok(Self {
+            header,
+            segments,
+            size,
+        })
+    }
+
+    /// Reads the ELF core file at `offset`.
+    pub fn read_at(&self, mut offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut written = 0;
+
+        while writer.avail() > 0 && offset < self.size {
+            let len = if offset < self.header.len() {
+                let mut reader = VmReader::from(&self.header[offset..]);
+                writer.write_fallible(&mut reader)?
+            } else if let Some(segment) = self.segments.iter().find(|segment| {
+                segment.offset <= offset && offset < segment.offset + segment.range.len()
+            }) {
+                let start = segment.range.start + (offset - segment.offset);
+                ostd::power::read_old_memory(start..segment.range.end, writer)?
+            } else {
+                // This is the padding between the segments.
+                let next = self
+                    .segments
+                    .iter()
+                    .map(|segment| segment.offset)
+                    .find(|&segment_offset| segment_offset > offset)
+                    .unwrap_or(self.size);
+                writer.fill_zeros(next - offset)?
+            };
+
+            if len == 0 {
+                break;
+            }
+            offset += len;
+            written += len;
+        }
+
+        Ok(written)
+    }
+}
+
+/// Parses the `[SIZE@]ADDRESS` argument of `elfcorehdr=`.
+fn parse_elfcorehdr(arg: &str) -> Option<Paddr> {
+    let addr = arg.split_once('@').map_or(arg, |(_, addr)| addr);
+    match addr.strip_prefix("0x") {
+        Some(hex) => Paddr::from_str_radix(hex, 16).ok(),
+        None => addr.parse().ok(),
+    }
+}
+
+fn read_old_memory_exact(addr: Paddr, buf: &mut [u8]) -> Result<()> {
+    let end = addr
+        .checked_add(buf.len())
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "the address overflows"))?;
+
+    let mut writer = VmWriter::from(buf).to_fallible();
+    let len = ostd::power::read_old_memory(addr..end, &mut writer)?;
+    if len != end - addr {
+        return_errno_with_message!(Errno::EIO, "the old memory cannot be read");
+    }
+
+    Ok(())
+}
+
+const ELF_MAGIC: &[u8; 4] = b"\x7fELF";
+const EI_CLASS: usize = 4;
+const EI_DATA: usize = 5;
+const EI_VERSION: usize = 6;
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+const EV_CURRENT: u8 = 1;
+const ET_CORE: u16 = 4;
+const PT_LOAD: u32 = 1;
+const PF_RWX: u32 = 0b111;
+
+/// The ELF header.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/include/uapi/linux/elf.h>
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct Elf64Ehdr {
+    e_ident: [u8; 16],
+    e_type: u16,
+    e_machine: u16,
+    e_version: u32,
+    e_entry: u64,
+    e_phoff: u64,
+    e_shoff: u64,
+    e_flags: u32,
+    e_ehsize: u16,
+    e_phentsize: u16,
+    e_phnum: u16,
+    e_shentsize: u16,
+    e_shnum: u16,
+    e_shstrndx: u16,
+}
+
+/// The ELF program header.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/include/uapi/linux/elf.h>
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct Elf64Phdr {
+    p_type: u32,
+    p_flags: u32,
+    p_offset: u64,
+    p_vaddr: u64,
+    p_paddr: u64,
+    p_filesz: u64,
+    p_memsz: u64,
+    p_align: u64,
+}
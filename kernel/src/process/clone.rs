@@ -4,8 +4,7 @@ use alloc::borrow::Cow;
 use core::{num::NonZeroU64, sync::atomic::Ordering};
 
 use ostd::{
-    arch::cpu::context::UserContext, cpu::CpuId, mm::VmIo, sync::RwArc, task::Task,
-    user::UserContextApi,
+    arch::cpu::context::UserContext, cpu::CpuId, mm::VmIo, task::Task, user::UserContextApi,
 };
 
 use super::{
@@ -20,7 +19,7 @@ use crate::{
     current_userspace,
     fs::{
         cgroupfs::{CgroupMembership, CgroupSysNode},
-        file::file_table::{FdFlags, FileTable},
+        file::file_table::{FdFlags, SharedFileTable},
         thread_info::ThreadFsInfo,
     },
     prelude::*,
@@ -386,6 +385,15 @@ fn clone_child_task(
     // Clone default timer slack
     let default_timer_slack_ns = posix_thread.timer_slack_ns();
 
+    // Inherit perf events
+    let child_perf_events = posix_thread.perf_events().inherit();
+
+    // Inherit the security attributes
+    let child_no_new_privs = posix_thread.no_new_privs();
+    let child_landlock_domain = posix_thread.landlock_domain();
+    let child_apparmor_profile = posix_thread.apparmor_context().lock().profile();
+    let child_keyrings = posix_thread.keyrings().lock().new_for_thread();
+
     if clone_flags.contains(CloneFlags::CLONE_NEWNS) {
         child_fs
             .resolver()
@@ -423,7 +431,12 @@ fn clone_child_task(
                 .fpu_context(child_fpu_context)
                 .user_ns(child_user_ns)
                 .ns_proxy(child_ns_proxy)
-                .default_timer_slack_ns(default_timer_slack_ns);
+                .default_timer_slack_ns(default_timer_slack_ns)
+                .perf_events(child_perf_events)
+                .no_new_privs(child_no_new_privs)
+                .landlock_domain(child_landlock_domain)
+                .apparmor_profile(child_apparmor_profile)
+                .keyrings(child_keyrings);
 
         // Deal with SETTID/CLEARTID flags
         clone_parent_settid(child_tid, clone_args.parent_tid, clone_flags)?;
@@ -500,6 +513,15 @@ fn clone_child_process(
     // Clone default timer slack
     let default_timer_slack_ns = posix_thread.timer_slack_ns();
 
+    // Inherit perf events
+    let child_perf_events = posix_thread.perf_events().inherit();
+
+    // Inherit the security attributes
+    let child_no_new_privs = posix_thread.no_new_privs();
+    let child_landlock_domain = posix_thread.landlock_domain();
+    let child_apparmor_profile = posix_thread.apparmor_context().lock().profile();
+    let child_keyrings = posix_thread.keyrings().lock().new_for_process();
+
     if clone_flags.contains(CloneFlags::CLONE_NEWNS) {
         child_fs
             .resolver()
@@ -547,6 +569,11 @@ fn clone_child_process(
                 .user_ns(child_user_ns.clone())
                 .ns_proxy(child_ns_proxy)
                 .default_timer_slack_ns(default_timer_slack_ns)
+                .perf_events(child_perf_events)
+                .no_new_privs(child_no_new_privs)
+                .landlock_domain(child_landlock_domain)
+                .apparmor_profile(child_apparmor_profile)
+                .keyrings(child_keyrings)
         };
 
         // Deal with SETTID/CLEARTID flags
@@ -664,13 +691,13 @@ fn clone_fs(parent_fs: &Arc<ThreadFsInfo>, clone_flags: CloneFlags) -> Arc<Threa
     }
 }
 
-fn clone_files(parent_file_table: &RwArc<FileTable>, clone_flags: CloneFlags) -> RwArc<FileTable> {
+fn clone_files(parent_file_table: &SharedFileTable, clone_flags: CloneFlags) -> SharedFileTable {
     // If CLONE_FILES is set, the child and parent share the same file table.
     // Otherwise, the child has a copy of the parent's file table.
     if clone_flags.contains(CloneFlags::CLONE_FILES) {
         parent_file_table.clone()
     } else {
-        RwArc::new(parent_file_table.read().clone())
+        SharedFileTable::new(parent_file_table.read().clone())
     }
 }
 
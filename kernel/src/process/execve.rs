@@ -10,15 +10,13 @@ use ostd::{
 
 use super::process_vm::activate_vmar;
 use crate::{
-    fs::vfs::{
-        inode::Inode,
-        path::{Path, PathResolver},
-    },
+    fs::vfs::path::{Path, PathResolver, PerMountFlags},
     prelude::*,
     process::{
         ContextUnshareAdminApi, Credentials, Process,
         posix_thread::{
-            ContextPthreadAdminApi, ThreadLocal, ThreadName, sigkill_other_threads, thread_table,
+            AsPosixThread, ContextPthreadAdminApi, ThreadLocal, ThreadName, sigkill_other_threads,
+            thread_table,
         },
         process_vm::{MAX_LEN_STRING_ARG, MAX_NR_STRING_ARGS, ProcessVm},
         program_loader::{ProgramToLoad, elf::ElfLoadInfo},
@@ -28,6 +26,7 @@ use crate::{
             signals::kernel::KernelSignal,
         },
     },
+    security::{audit, lsm},
     vm::vmar::Vmar,
 };
 
@@ -56,6 +55,9 @@ pub fn do_execve(
         envp
     );
 
+    audit::log_execve(ctx, &argv);
+    lsm::bprm_creds_for_exec(&elf_file)?;
+
     let program_to_load =
         ProgramToLoad::build_from_file(elf_file.clone(), &path_resolver, argv, envp)?;
 
@@ -162,7 +164,8 @@ fn do_execve_no_return(
     // This prevents race conditions when checking access permissions while opening
     // `/proc/[pid]/mem` or `/proc/[pid]/maps`.
     let vmar_guard = activate_vmar(ctx, new_vmar);
-    apply_caps_from_exec(process, ctx.credentials_mut(), elf_file.inode())?;
+    apply_caps_from_exec(process, ctx.credentials_mut(), &elf_file)?;
+    lsm::bprm_committing_creds();
     drop(vmar_guard);
 
     // After the program has been successfully loaded, the virtual memory of the current process
@@ -189,6 +192,10 @@ fn do_execve_no_return(
     *thread_local.sig_stack().borrow_mut() = SigStack::default();
     // Restore the process exit signal to SIGCHLD.
     process.set_exit_signal(SIGCHLD);
+    // Enable the perf events that should be enabled on `execve`.
+    posix_thread.perf_events().enable_on_exec();
+    // Drop the thread keyring and the process keyring.
+    posix_thread.keyrings().lock().reset_on_exec();
 
     Ok(())
 }
@@ -248,32 +255,32 @@ fn set_cpu_context(
     debug!("user stack top: 0x{:x}", elf_load_info.user_stack_top);
 }
 
-/// Sets the UID and GID in the credentials according to the ELF inode.
+/// Sets the UID and GID in the credentials according to the ELF file.
 ///
 /// The capabilities will be updated accordingly.
 fn apply_caps_from_exec(
     current: &Process,
     credentials: Credentials<ReadWriteOp>,
-    elf_inode: &Arc<dyn Inode>,
+    elf_file: &Path,
 ) -> Result<()> {
-    set_uid_from_elf(current, &credentials, elf_inode)?;
-    set_gid_from_elf(current, &credentials, elf_inode)?;
+    set_uid_from_elf(current, &credentials, elf_file)?;
+    set_gid_from_elf(current, &credentials, elf_file)?;
     credentials.set_keep_capabilities(false)?;
 
     Ok(())
 }
 
-/// Sets the UID in the credentials according to the ELF inode.
+/// Sets the UID in the credentials according to the ELF file.
 ///
-/// If the ELF inode has the `set_uid` bit, the effective UID is set to the same value as the ELF
-/// inode's UID.
+/// If the ELF file has the `set_uid` bit, the effective UID is set to the same value as the ELF
+/// file's UID.
 fn set_uid_from_elf(
     current: &Process,
     credentials: &Credentials<ReadWriteOp>,
-    elf_inode: &Arc<dyn Inode>,
+    elf_file: &Path,
 ) -> Result<()> {
-    if elf_inode.mode()?.has_set_uid() {
-        let uid = elf_inode.owner()?;
+    if elf_file.mode()?.has_set_uid() && !is_set_id_ignored(elf_file) {
+        let uid = elf_file.owner()?;
         credentials.set_euid(uid);
 
         current.clear_parent_death_signal();
@@ -284,17 +291,17 @@ fn set_uid_from_elf(
     Ok(())
 }
 
-/// Sets the GID in the credentials according to the ELF inode.
+/// Sets the GID in the credentials according to the ELF file.
 ///
-/// If the ELF inode has the `set_gid` bit, the effective GID is set to the same value as the ELF
-/// inode's GID.
+/// If the ELF file has the `set_gid` bit, the effective GID is set to the same value as the ELF
+/// file's GID.
 fn set_gid_from_elf(
     current: &Process,
     credentials: &Credentials<ReadWriteOp>,
-    elf_inode: &Arc<dyn Inode>,
+    elf_file: &Path,
 ) -> Result<()> {
-    if elf_inode.mode()?.has_set_gid() {
-        let gid = elf_inode.group()?;
+    if elf_file.mode()?.has_set_gid() && !is_set_id_ignored(elf_file) {
+        let gid = elf_file.group()?;
         credentials.set_egid(gid);
 
         current.clear_parent_death_signal();
@@ -305,6 +312,15 @@ fn set_gid_from_elf(
     Ok(())
 }
 
+/// Returns whether the set-user-ID and set-group-ID bits of the file should be ignored.
+///
+/// The bits are ignored if the file is on a `nosuid` mount, or if the `no_new_privs` attribute
+/// of the current thread is set.
+pub(super) fn is_set_id_ignored(file: &Path) -> bool {
+    file.mount_flags().contains(PerMountFlags::NOSUID)
+        || current_thread!().as_posix_thread().unwrap().no_new_privs()
+}
+
 fn reset_vfork_child(process: &Process) {
     if process.status().is_vfork_child() {
         // Resumes the parent process.
@@ -2,7 +2,7 @@
 
 use core::sync::atomic::Ordering;
 
-use super::{Pid, Process, process_table};
+use super::{Pid, Process, process::release_terminal_on_exit, process_table};
 use crate::{
     events::IoEvents, fs::cgroupfs::CgroupMembership, prelude::*,
     process::signal::signals::kernel::KernelSignal,
@@ -24,6 +24,8 @@ pub(super) fn exit_process(current_process: &Process) {
 
     current_process.pidfile_pollee.notify(IoEvents::IN);
 
+    release_terminal_on_exit(current_process);
+
     send_parent_death_signal(current_process);
 
     move_children_to_reaper_process(current_process);
@@ -9,6 +9,7 @@ use super::{
 use crate::{
     prelude::*,
     process::{credentials::capabilities::CapSet, posix_thread::PosixThread},
+    security::lsm,
     thread::Tid,
 };
 
@@ -145,8 +146,17 @@ fn kill_process(process: &Process, signal: Option<Box<dyn Signal>>, ctx: &Contex
     Ok(())
 }
 
-// Reference: <https://elixir.bootlin.com/linux/v6.17/source/kernel/signal.c#L799>.
 fn check_signal_perm(target: &PosixThread, ctx: &Context, signum: Option<SigNum>) -> Result<()> {
+    check_signal_cred_perm(target, ctx, signum)?;
+    lsm::task_kill(target, signum)
+}
+
+// Reference: <https://elixir.bootlin.com/linux/v6.17/source/kernel/signal.c#L799>.
+fn check_signal_cred_perm(
+    target: &PosixThread,
+    ctx: &Context,
+    signum: Option<SigNum>,
+) -> Result<()> {
     let target_process = target.process();
 
     if Arc::ptr_eq(&target_process, &ctx.process) {
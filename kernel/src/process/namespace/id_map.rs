@@ -0,0 +1,83 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::prelude::*;
+
+/// The mapping of user or group IDs between a user namespace and its parent.
+///
+/// An ID map consists of a number of non-overlapping extents. Each extent maps a
+/// contiguous range of IDs inside the namespace to a contiguous range of IDs of
+/// the same length outside the namespace (i.e., in the parent user namespace).
+///
+/// Reference: <https://man7.org/linux/man-pages/man7/user_namespaces.7.html>
+#[derive(Debug, Clone)]
+pub struct IdMap {
+    extents: Vec<IdMapExtent>,
+}
+
+/// An extent of an [`IdMap`], which is a line in `/proc/[pid]/uid_map` or `/proc/[pid]/gid_map`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdMapExtent {
+    /// The first ID of the range inside the namespace.
+    first: u32,
+    /// The first ID of the range outside the namespace.
+    lower_first: u32,
+    /// The length of the range.
+    count: u32,
+}
+
+impl IdMap {
+    /// Creates the identity map that is used by the initial user namespace.
+    ///
+    /// The map covers all IDs except `u32::MAX`, which is reserved as the invalid ID.
+    pub(super) fn new_identity() -> Self {
+        Self {
+            extents: vec![IdMapExtent {
+                first: 0,
+                lower_first: 0,
+                count: u32::MAX,
+            }],
+        }
+    }
+
+    /// Maps an ID inside the namespace to the corresponding ID outside the namespace.
+    ///
+    /// Returns `None` if the ID is not covered by this map.
+    pub fn map_down(&self, id: u32) -> Option<u32> {
+        self.extents.iter().find_map(|extent| {
+            let offset = id.checked_sub(extent.first)?;
+            (offset < extent.count).then(|| extent.lower_first + offset)
+        })
+    }
+
+    /// Maps an ID outside the namespace to the corresponding ID inside the namespace.
+    ///
+    /// Returns `None` if the ID is not covered by this map.
+    pub fn map_up(&self, lower_id: u32) -> Option<u32> {
+        self.extents.iter().find_map(|extent| {
+            let offset = lower_id.checked_sub(extent.lower_first)?;
+            (offset < extent.count).then(|| extent.first + offset)
+        })
+    }
+
+    /// Returns the extents of this map.
+    pub fn extents(&self) -> &[IdMapExtent] {
+        &self.extents
+    }
+}
+
+impl IdMapExtent {
+    /// Returns the first ID of the range inside the namespace.
+    pub fn first(&self) -> u32 {
+        self.first
+    }
+
+    /// Returns the first ID of the range outside the namespace.
+    pub fn lower_first(&self) -> u32 {
+        self.lower_first
+    }
+
+    /// Returns the length of the range.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+}
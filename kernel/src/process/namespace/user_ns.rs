@@ -2,14 +2,18 @@
 
 use spin::Once;
 
+use super::id_map::IdMap;
 use crate::{
     fs::pseudofs::{NsCommonOps, NsType, StashedDentry},
     prelude::*,
-    process::{Uid, credentials::capabilities::CapSet, posix_thread::PosixThread},
+    process::{Gid, Uid, credentials::capabilities::CapSet, posix_thread::PosixThread},
+    security::lsm,
 };
 
 /// The user namespace.
 pub struct UserNamespace {
+    uid_map: IdMap,
+    gid_map: IdMap,
     stashed_dentry: StashedDentry,
 }
 
@@ -20,6 +24,8 @@ impl UserNamespace {
 
         INIT.call_once(|| {
             Arc::new(Self {
+                uid_map: IdMap::new_identity(),
+                gid_map: IdMap::new_identity(),
                 stashed_dentry: StashedDentry::new(),
             })
         })
@@ -34,7 +40,7 @@ impl UserNamespace {
         // we should verify the thread's capabilities within the relevant user namespace.
         let cap_set = posix_thread.credentials().effective_capset();
         if cap_set.contains(required) {
-            return Ok(());
+            return lsm::capable(posix_thread, required);
         }
 
         return_errno_with_message!(
@@ -50,6 +56,41 @@ impl UserNamespace {
         Ok(Uid::new_root())
     }
 
+    /// Returns whether this is the initial user namespace.
+    pub fn is_init(self: &Arc<Self>) -> bool {
+        Arc::ptr_eq(self, Self::get_init_singleton())
+    }
+
+    /// Returns the UID map of the user namespace.
+    pub fn uid_map(&self) -> &IdMap {
+        &self.uid_map
+    }
+
+    /// Returns the GID map of the user namespace.
+    pub fn gid_map(&self) -> &IdMap {
+        &self.gid_map
+    }
+
+    /// Maps a UID inside the namespace to the corresponding UID in the initial namespace.
+    pub fn map_uid_down(&self, uid: Uid) -> Option<Uid> {
+        self.uid_map.map_down(uid.into()).map(Uid::new)
+    }
+
+    /// Maps a UID in the initial namespace to the corresponding UID inside the namespace.
+    pub fn map_uid_up(&self, uid: Uid) -> Option<Uid> {
+        self.uid_map.map_up(uid.into()).map(Uid::new)
+    }
+
+    /// Maps a GID inside the namespace to the corresponding GID in the initial namespace.
+    pub fn map_gid_down(&self, gid: Gid) -> Option<Gid> {
+        self.gid_map.map_down(gid.into()).map(Gid::new)
+    }
+
+    /// Maps a GID in the initial namespace to the corresponding GID inside the namespace.
+    pub fn map_gid_up(&self, gid: Gid) -> Option<Gid> {
+        self.gid_map.map_up(gid.into()).map(Gid::new)
+    }
+
     /// Returns whether this namespace is the same as, or an ancestor of, the other namespace.
     pub fn is_same_or_ancestor_of(self: &Arc<Self>, other: &Arc<Self>) -> bool {
         // FIXME: Creating new user namespaces is not yet supported,
@@ -1,17 +1,20 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use core::sync::atomic::{AtomicU32, AtomicU64};
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicU64};
 
 use ostd::{
     arch::cpu::context::{FpuContext, UserContext},
     cpu::CpuSet,
-    sync::RwArc,
     task::Task,
 };
 
 use super::{PosixThread, ThreadLocal, thread_table};
 use crate::{
-    fs::{file::file_table::FileTable, thread_info::ThreadFsInfo},
+    fs::{
+        file::file_table::{FileTable, SharedFileTable},
+        thread_info::ThreadFsInfo,
+    },
+    perf::PerfEventContext,
     prelude::*,
     process::{
         Credentials, NsProxy, Process, UserNamespace,
@@ -19,6 +22,11 @@ use crate::{
         signal::{sig_mask::AtomicSigMask, sig_queues::SigQueues},
     },
     sched::{Nice, SchedPolicy},
+    security::{
+        apparmor::{AppArmorContext, Profile},
+        keys::KeyringContext,
+        landlock::LandlockDomain,
+    },
     thread::{Thread, Tid, task},
     time::{TimerManager, clocks::ProfClock},
 };
@@ -35,7 +43,7 @@ pub struct PosixThreadBuilder {
     // Optional part
     set_child_tid: Vaddr,
     clear_child_tid: Vaddr,
-    file_table: Option<RwArc<FileTable>>,
+    file_table: Option<SharedFileTable>,
     fs: Option<Arc<ThreadFsInfo>>,
     sig_mask: AtomicSigMask,
     sig_queues: SigQueues,
@@ -45,6 +53,11 @@ pub struct PosixThreadBuilder {
     ns_proxy: Option<Arc<NsProxy>>,
     is_init_process: bool,
     default_timer_slack_ns: u64,
+    perf_events: PerfEventContext,
+    no_new_privs: bool,
+    landlock_domain: Option<Arc<LandlockDomain>>,
+    apparmor_profile: Option<Arc<Profile>>,
+    keyrings: KeyringContext,
 }
 
 impl PosixThreadBuilder {
@@ -72,6 +85,11 @@ impl PosixThreadBuilder {
             user_ns: None,
             ns_proxy: None,
             default_timer_slack_ns: 50_000, // 50 usec default slack
+            perf_events: PerfEventContext::new(),
+            no_new_privs: false,
+            landlock_domain: None,
+            apparmor_profile: None,
+            keyrings: KeyringContext::new(),
         }
     }
 
@@ -90,7 +108,7 @@ impl PosixThreadBuilder {
         self
     }
 
-    pub fn file_table(mut self, file_table: RwArc<FileTable>) -> Self {
+    pub fn file_table(mut self, file_table: SharedFileTable) -> Self {
         self.file_table = Some(file_table);
         self
     }
@@ -125,6 +143,31 @@ impl PosixThreadBuilder {
         self
     }
 
+    pub fn perf_events(mut self, perf_events: PerfEventContext) -> Self {
+        self.perf_events = perf_events;
+        self
+    }
+
+    pub fn no_new_privs(mut self, no_new_privs: bool) -> Self {
+        self.no_new_privs = no_new_privs;
+        self
+    }
+
+    pub(crate) fn landlock_domain(mut self, landlock_domain: Option<Arc<LandlockDomain>>) -> Self {
+        self.landlock_domain = landlock_domain;
+        self
+    }
+
+    pub(crate) fn apparmor_profile(mut self, apparmor_profile: Option<Arc<Profile>>) -> Self {
+        self.apparmor_profile = apparmor_profile;
+        self
+    }
+
+    pub(crate) fn keyrings(mut self, keyrings: KeyringContext) -> Self {
+        self.keyrings = keyrings;
+        self
+    }
+
     #[expect(clippy::wrong_self_convention)]
     pub(in crate::process) fn is_init_process(mut self) -> Self {
         self.is_init_process = true;
@@ -150,9 +193,14 @@ impl PosixThreadBuilder {
             ns_proxy,
             is_init_process,
             default_timer_slack_ns,
+            perf_events,
+            no_new_privs,
+            landlock_domain,
+            apparmor_profile,
+            keyrings,
         } = self;
 
-        let file_table = file_table.unwrap_or_else(|| RwArc::new(FileTable::new()));
+        let file_table = file_table.unwrap_or_else(|| SharedFileTable::new(FileTable::new()));
 
         assert_eq!(user_ns.is_none(), ns_proxy.is_none());
         let user_ns = user_ns.unwrap_or_else(|| UserNamespace::get_init_singleton().clone());
@@ -187,6 +235,11 @@ impl PosixThreadBuilder {
                     ns_proxy: Mutex::new(Some(ns_proxy.clone())),
                     timer_slack_ns: AtomicU64::new(default_timer_slack_ns),
                     default_timer_slack_ns: AtomicU64::new(default_timer_slack_ns),
+                    perf_events,
+                    no_new_privs: AtomicBool::new(no_new_privs),
+                    landlock_domain: RwLock::new(landlock_domain),
+                    apparmor_context: SpinLock::new(AppArmorContext::new(apparmor_profile)),
+                    keyrings: SpinLock::new(keyrings),
                 }
             };
 
@@ -80,6 +80,11 @@ fn exit_internal(term_status: TermStatus, is_exiting_group: bool) {
         thread_table::remove_thread(posix_thread.tid());
     }
 
+    // If the file table is already gone, there are no open files whose locks can be released.
+    if is_last_thread && let Some(file_table) = thread_local.borrow_file_table().get() {
+        file_table.read().release_range_locks();
+    }
+
     // Drop fields in `PosixThread`.
     *posix_thread.file_table().lock() = None;
     *posix_thread.ns_proxy().lock() = None;
@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 
 use aster_rights::{ReadDupOp, ReadOp, ReadWriteOp};
 use ostd::{
@@ -15,12 +15,14 @@ use super::{
 use crate::{
     events::IoEvents,
     fs::{file::file_table::FileTable, thread_info::ThreadFsInfo},
+    perf::PerfEventContext,
     prelude::*,
     process::{
         Pid,
         namespace::nsproxy::NsProxy,
         signal::{PauseReason, PollHandle, sig_mask::SigMask},
     },
+    security::{apparmor::AppArmorContext, keys::KeyringContext, landlock::LandlockDomain},
     thread::{Thread, Tid},
     time::{Timer, TimerManager, clocks::ProfClock, timer::TimerGuard},
 };
@@ -91,6 +93,21 @@ pub struct PosixThread {
     timer_slack_ns: AtomicU64,
     /// The default timer slack value for this thread.
     default_timer_slack_ns: AtomicU64,
+
+    /// The perf events that are attached to this thread.
+    perf_events: PerfEventContext,
+
+    // Security
+    /// Whether `execve` is prevented from granting privileges that the thread does not have.
+    ///
+    /// Once set, this attribute cannot be unset, and it is inherited by new threads.
+    no_new_privs: AtomicBool,
+    /// The Landlock domain that restricts the thread, if any.
+    landlock_domain: RwLock<Option<Arc<LandlockDomain>>>,
+    /// The AppArmor attributes of the thread.
+    apparmor_context: SpinLock<AppArmorContext>,
+    /// The keyrings of the thread.
+    keyrings: SpinLock<KeyringContext>,
 }
 
 impl PosixThread {
@@ -317,6 +334,43 @@ impl PosixThread {
         let default = self.default_timer_slack_ns.load(Ordering::Relaxed);
         self.timer_slack_ns.store(default, Ordering::Relaxed);
     }
+
+    /// Returns the perf events that are attached to this thread.
+    pub fn perf_events(&self) -> &PerfEventContext {
+        &self.perf_events
+    }
+
+    /// Returns whether the `no_new_privs` attribute of the thread is set.
+    pub fn no_new_privs(&self) -> bool {
+        self.no_new_privs.load(Ordering::Relaxed)
+    }
+
+    /// Sets the `no_new_privs` attribute of the thread.
+    pub fn set_no_new_privs(&self) {
+        self.no_new_privs.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns the Landlock domain of the thread.
+    pub(crate) fn landlock_domain(&self) -> Option<Arc<LandlockDomain>> {
+        self.landlock_domain.read().clone()
+    }
+
+    /// Sets the Landlock domain of the thread.
+    ///
+    /// The new domain must be built on top of the old domain, so it only adds restrictions.
+    pub(crate) fn set_landlock_domain(&self, domain: Arc<LandlockDomain>) {
+        *self.landlock_domain.write() = Some(domain);
+    }
+
+    /// Returns the AppArmor attributes of the thread.
+    pub(crate) fn apparmor_context(&self) -> &SpinLock<AppArmorContext> {
+        &self.apparmor_context
+    }
+
+    /// Returns the keyrings of the thread.
+    pub(crate) fn keyrings(&self) -> &SpinLock<KeyringContext> {
+        &self.keyrings
+    }
 }
 
 /// Provides administrative APIs for the current POSIX thread.
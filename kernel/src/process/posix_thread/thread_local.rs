@@ -2,16 +2,17 @@
 
 use core::cell::{Cell, Ref, RefCell, RefMut};
 
-use ostd::{arch::cpu::context::FpuContext, mm::Vaddr, sync::RwArc, task::CurrentTask};
+use ostd::{arch::cpu::context::FpuContext, mm::Vaddr, task::CurrentTask};
 
 use super::RobustListHead;
 use crate::{
-    fs::{file::file_table::FileTable, thread_info::ThreadFsInfo},
+    fs::{file::file_table::SharedFileTable, thread_info::ThreadFsInfo},
     prelude::*,
     process::{
         NsProxy, UserNamespace,
         signal::{SigStack, sig_mask::SigMask},
     },
+    security::audit::AuditContext,
     vm::vmar::Vmar,
 };
 
@@ -32,7 +33,7 @@ pub struct ThreadLocal {
 
     // Files.
     /// File table.
-    file_table: RefCell<Option<RwArc<FileTable>>>,
+    file_table: RefCell<Option<SharedFileTable>>,
     /// File system.
     fs: RefCell<Arc<ThreadFsInfo>>,
 
@@ -50,6 +51,10 @@ pub struct ThreadLocal {
     // Namespaces.
     user_ns: RefCell<Arc<UserNamespace>>,
     ns_proxy: RefCell<Option<Arc<NsProxy>>>,
+
+    // Audit.
+    /// The records of the current system call that may be audited.
+    audit_context: RefCell<AuditContext>,
 }
 
 impl ThreadLocal {
@@ -58,7 +63,7 @@ impl ThreadLocal {
         set_child_tid: Vaddr,
         clear_child_tid: Vaddr,
         vmar: Arc<Vmar>,
-        file_table: RwArc<FileTable>,
+        file_table: SharedFileTable,
         fs: Arc<ThreadFsInfo>,
         fpu_context: FpuContext,
         user_ns: Arc<UserNamespace>,
@@ -78,6 +83,7 @@ impl ThreadLocal {
             sig_mask_saved: Cell::new(None),
             user_ns: RefCell::new(user_ns),
             ns_proxy: RefCell::new(Some(ns_proxy)),
+            audit_context: RefCell::new(AuditContext::default()),
         }
     }
 
@@ -181,6 +187,10 @@ impl ThreadLocal {
     pub(in crate::process) fn borrow_ns_proxy_mut(&self) -> NsProxyRefMut<'_> {
         ThreadLocalOptionRefMut(self.ns_proxy.borrow_mut())
     }
+
+    pub(crate) fn audit_context(&self) -> &RefCell<AuditContext> {
+        &self.audit_context
+    }
 }
 
 /// The current state of `ThreadFpu`.
@@ -270,7 +280,7 @@ impl ThreadFpu<'_> {
 }
 
 /// An immutable, shared reference to the file table in [`ThreadLocal`].
-pub type FileTableRef<'a> = ThreadLocalOptionRef<'a, RwArc<FileTable>>;
+pub type FileTableRef<'a> = ThreadLocalOptionRef<'a, SharedFileTable>;
 
 /// An immutable, shared reference to the `NsProxy` in [`ThreadLocal`].
 pub type NsProxyRef<'a> = ThreadLocalOptionRef<'a, Arc<NsProxy>>;
@@ -287,10 +297,15 @@ impl<T> ThreadLocalOptionRef<'_, T> {
     pub fn unwrap(&self) -> &T {
         self.0.as_ref().unwrap()
     }
+
+    /// Returns a reference to the data, or `None` if the data has been dropped.
+    pub fn get(&self) -> Option<&T> {
+        self.0.as_ref()
+    }
 }
 
 /// A mutable, exclusive reference to the file table in [`ThreadLocal`].
-pub type FileTableRefMut<'a> = ThreadLocalOptionRefMut<'a, RwArc<FileTable>>;
+pub type FileTableRefMut<'a> = ThreadLocalOptionRefMut<'a, SharedFileTable>;
 
 /// A mutable, exclusive reference to the `NsProxy` in [`ThreadLocal`].
 pub(in crate::process) type NsProxyRefMut<'a> = ThreadLocalOptionRefMut<'a, Arc<NsProxy>>;
@@ -1,9 +1,18 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use ostd::sync::{LocalIrqDisabled, WaitQueue};
+use ostd::sync::LocalIrqDisabled;
 
 use super::{ProcessGroup, Session};
-use crate::prelude::*;
+use crate::{
+    prelude::*,
+    process::{
+        posix_thread::AsPosixThread,
+        signal::{
+            constants::SIGTTIN, sig_action::SigAction, sig_num::SigNum,
+            signals::kernel::KernelSignal,
+        },
+    },
+};
 
 /// The job control for terminals like TTY and pty.
 ///
@@ -13,7 +22,6 @@ use crate::prelude::*;
 /// for a terminal.
 pub struct JobControl {
     inner: SpinLock<Inner, LocalIrqDisabled>,
-    wait_queue: WaitQueue,
 }
 
 #[derive(Default)]
@@ -27,7 +35,6 @@ impl JobControl {
     pub fn new() -> Self {
         Self {
             inner: SpinLock::new(Inner::default()),
-            wait_queue: WaitQueue::new(),
         }
     }
 
@@ -111,24 +118,28 @@ impl JobControl {
             &inner.session.upgrade().unwrap()
         ));
         inner.foreground = Arc::downgrade(process_group);
-
-        self.wait_queue.wake_all();
     }
 
-    /// Waits until the current process is in the foreground process group.
+    /// Checks whether the current process can access the terminal.
     ///
-    /// Note that we only wait if the terminal is the our controlling terminal. If it isn't, the
-    /// method returns immediately without an error. This should match the Linux behavior where the
-    /// SIGTTIN won't be sent if we're reading the terminal that is the controlling terminal of
-    /// another session.
+    /// If the terminal is our controlling terminal but we are in a background process group,
+    /// `signum` (i.e., `SIGTTIN` for reads and `SIGTTOU` for writes) will be sent to our process
+    /// group, and this method will fail with `ERESTARTSYS` so that the system call can be
+    /// restarted after the process group is continued.
+    ///
+    /// If `signum` is blocked or ignored, reads will fail with `EIO` but writes will be allowed.
+    /// If our process group is orphaned, no one can continue it after it is stopped, so this
+    /// method will fail with `EIO` instead.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/tty/tty_jobctrl.c>.
     ///
     /// # Panics
     ///
     /// This method will panic if it is not called in the process context.
-    pub fn wait_until_in_foreground(&self) -> Result<()> {
+    pub fn check_background_access(&self, signum: SigNum) -> Result<()> {
         let current = current!();
 
-        self.wait_queue.pause_until(|| {
+        let process_group = {
             let process_group_mut = current.process_group.lock();
             let process_group = process_group_mut.upgrade().unwrap();
             let session = process_group.session().unwrap();
@@ -139,16 +150,53 @@ impl JobControl {
                 .upgrade()
                 .is_some_and(|terminal_session| Arc::ptr_eq(&terminal_session, &session))
             {
-                // The terminal is not our controlling terminal. Don't wait.
-                return Some(());
+                // The terminal is not our controlling terminal.
+                return Ok(());
             }
 
-            inner
+            if inner
                 .foreground
                 .upgrade()
-                .is_some_and(|terminal_foregroup| Arc::ptr_eq(&terminal_foregroup, &process_group))
-                .then_some(())
-        })
+                .is_none_or(|terminal_foreground| Arc::ptr_eq(&terminal_foreground, &process_group))
+            {
+                // We are in the foreground process group, or there is no foreground process
+                // group at all.
+                return Ok(());
+            }
+
+            process_group
+        };
+
+        let is_blocked = current_thread!()
+            .as_posix_thread()
+            .unwrap()
+            .has_signal_blocked(signum);
+        let is_ignored = matches!(
+            current.sig_dispositions().lock().lock().get(signum),
+            SigAction::Ign
+        );
+        if is_blocked || is_ignored {
+            if signum == SIGTTIN {
+                return_errno_with_message!(
+                    Errno::EIO,
+                    "the background process group cannot read from the terminal"
+                );
+            }
+            return Ok(());
+        }
+
+        if process_group.is_orphaned() {
+            return_errno_with_message!(
+                Errno::EIO,
+                "the orphaned process group cannot access the terminal in the background"
+            );
+        }
+
+        process_group.broadcast_signal(KernelSignal::new(signum));
+        return_errno_with_message!(
+            Errno::ERESTARTSYS,
+            "the background process group is signaled to stop"
+        );
     }
 }
 
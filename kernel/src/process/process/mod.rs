@@ -51,6 +51,7 @@ use ostd::{
 pub use process_group::ProcessGroup;
 pub use session::Session;
 pub use terminal::Terminal;
+pub(super) use terminal::release_terminal_on_exit;
 
 /// Process id.
 pub type Pid = u32;
@@ -74,7 +75,7 @@ pub(super) fn init_in_first_process(ctx: &Context) {
     (crate::device::tty::SystemConsole::singleton()
         .terminal()
         .clone() as Arc<dyn Terminal>)
-        .set_control(ctx.process.as_ref())
+        .set_control(ctx.process.as_ref(), false)
         .unwrap();
 }
 
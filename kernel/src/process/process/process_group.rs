@@ -52,6 +52,40 @@ impl ProcessGroup {
         self.session.upgrade()
     }
 
+    /// Returns whether the process group is orphaned.
+    ///
+    /// A process group is orphaned if no process in it has a parent that belongs to a different
+    /// process group in the same session. Such a process group cannot be controlled by the job
+    /// control of a shell.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.18/source/kernel/exit.c>.
+    pub fn is_orphaned(self: &Arc<Self>) -> bool {
+        let processes: Vec<Arc<Process>> = self.inner.lock().processes.values().cloned().collect();
+        let session = self.session();
+
+        processes.iter().all(|process| {
+            if process.status().is_zombie() {
+                return true;
+            }
+
+            let Some(parent) = process.parent().lock().process().upgrade() else {
+                return true;
+            };
+            if parent.is_init_process() {
+                return true;
+            }
+
+            let Some(parent_group) = parent.process_group.lock().upgrade() else {
+                return true;
+            };
+            let is_same_session = match (parent_group.session(), session.as_ref()) {
+                (Some(parent_session), Some(session)) => Arc::ptr_eq(&parent_session, session),
+                _ => false,
+            };
+            Arc::ptr_eq(&parent_group, self) || !is_same_session
+        })
+    }
+
     /// Acquires a lock on the process group.
     pub fn lock(&self) -> ProcessGroupGuard<'_> {
         ProcessGroupGuard {
@@ -5,8 +5,17 @@ use alloc::sync::Arc;
 use super::{JobControl, Pgid, Process, Session, session::SessionGuard};
 use crate::{
     device::Device,
-    prelude::{Errno, Error, Result, current, return_errno_with_message, warn},
-    process::process_table,
+    prelude::{Box, Errno, Error, Result, current, current_thread, return_errno_with_message},
+    process::{
+        UserNamespace,
+        credentials::capabilities::CapSet,
+        posix_thread::AsPosixThread,
+        process_table,
+        signal::{
+            constants::{SIGCONT, SIGHUP, SIGTTOU},
+            signals::kernel::KernelSignal,
+        },
+    },
     util::ioctl::{RawIoctl, dispatch_ioctl},
 };
 
@@ -66,6 +75,17 @@ impl dyn Terminal {
                 cmd.write(&pgid)?;
             }
             cmd @ SetForegroundPgid => {
+                // Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/tty/tty_jobctrl.c>.
+                match self.job_control().check_background_access(SIGTTOU) {
+                    Err(err) if err.error() == Errno::EIO => {
+                        return_errno_with_message!(
+                            Errno::ENOTTY,
+                            "the orphaned process group cannot set the foreground process group"
+                        );
+                    }
+                    res => res?,
+                }
+
                 let pgid = cmd.read()?;
                 if pgid.cast_signed() < 0 {
                     return_errno_with_message!(Errno::EINVAL, "negative PGIDs are not valid");
@@ -76,11 +96,15 @@ impl dyn Terminal {
 
             // Commands about sessions
             cmd @ SetControlTty => {
-                if cmd.get() == 1 {
-                    warn!("stealing TTY from another session is not supported");
-                }
-
-                self.set_control(&current!())?;
+                let can_steal = cmd.get() == 1
+                    && UserNamespace::get_init_singleton()
+                        .check_cap(
+                            CapSet::SYS_ADMIN,
+                            current_thread!().as_posix_thread().unwrap(),
+                        )
+                        .is_ok();
+
+                self.set_control(&current!(), can_steal)?;
             }
             _cmd @ SetControlNoTty => {
                 if via_master {
@@ -120,7 +144,10 @@ impl dyn Terminal {
     }
 
     /// Sets the terminal to be the controlling terminal of the process.
-    pub(super) fn set_control(self: Arc<Self>, process: &Process) -> Result<()> {
+    ///
+    /// If the terminal is already the controlling terminal of another session, it will be stolen
+    /// from that session if `can_steal` is true. Otherwise, this method fails with `EPERM`.
+    pub(super) fn set_control(self: Arc<Self>, process: &Process, can_steal: bool) -> Result<()> {
         // Lock order: group of process -> session inner -> job control
         let process_group_mut = process.process_group.lock();
 
@@ -146,6 +173,20 @@ impl dyn Terminal {
             );
         }
 
+        if let Some(old_session) = self.job_control().session() {
+            if !can_steal {
+                return_errno_with_message!(
+                    Errno::EPERM,
+                    "the terminal is already a controlling terminal of another session"
+                );
+            }
+
+            // FIXME: Two session locks are held at the same time. This may deadlock if two
+            // processes steal the controlling terminals of each other's sessions concurrently.
+            old_session.lock().set_terminal(None);
+            self.job_control().unset_session();
+        }
+
         self.job_control().set_session(&session, &process_group)?;
         session_inner.set_terminal(Some(self));
 
@@ -167,10 +208,6 @@ impl dyn Terminal {
 
             session_inner.set_terminal(None);
             if let Some(foreground) = self.job_control().unset_session() {
-                use crate::process::signal::{
-                    constants::{SIGCONT, SIGHUP},
-                    signals::kernel::KernelSignal,
-                };
                 // FIXME: Correct the lock order here. We cannot lock the group inner after locking
                 // the session inner.
                 foreground.broadcast_signal(KernelSignal::new(SIGHUP));
@@ -181,6 +218,37 @@ impl dyn Terminal {
         })
     }
 
+    /// Hangs up the terminal.
+    ///
+    /// The terminal will no longer be the controlling terminal of its session, and the session
+    /// leader will receive `SIGHUP` and `SIGCONT`. This happens when, e.g., the pseudoterminal
+    /// master is closed.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/tty/tty_jobctrl.c>.
+    pub fn hang_up(self: Arc<Self>) {
+        let Some(session) = self.job_control().session() else {
+            return;
+        };
+
+        // Lock order: session inner -> job control
+        let mut session_inner = session.lock();
+        if !session_inner
+            .terminal()
+            .is_some_and(|session_terminal| Arc::ptr_eq(session_terminal, &self))
+        {
+            return;
+        }
+        session_inner.set_terminal(None);
+        self.job_control().unset_session();
+        drop(session_inner);
+
+        let Some(leader) = process_table::get_process(session.sid()) else {
+            return;
+        };
+        leader.enqueue_signal(Box::new(KernelSignal::new(SIGHUP)));
+        leader.enqueue_signal(Box::new(KernelSignal::new(SIGCONT)));
+    }
+
     /// Sets the foreground process group of the terminal.
     fn set_foreground(self: Arc<Self>, pgid: Pgid, process: &Process) -> Result<()> {
         // Lock order: group table -> group of process -> session inner -> job control
@@ -235,3 +303,35 @@ impl dyn Terminal {
         op(&session, &mut session_inner)
     }
 }
+
+/// Releases the controlling terminal if the exiting process is the session leader.
+///
+/// The foreground process group of the terminal will receive `SIGHUP`.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/tty/tty_jobctrl.c>.
+pub(in crate::process) fn release_terminal_on_exit(process: &Process) {
+    // Lock order: group of process -> session inner -> job control
+    let process_group_mut = process.process_group.lock();
+    let Some(process_group) = process_group_mut.upgrade() else {
+        return;
+    };
+    let Some(session) = process_group.session() else {
+        return;
+    };
+    if !session.is_leader(process) {
+        return;
+    }
+
+    let mut session_inner = session.lock();
+    let Some(terminal) = session_inner.terminal().cloned() else {
+        return;
+    };
+    session_inner.set_terminal(None);
+    let foreground = terminal.job_control().unset_session();
+    drop(session_inner);
+    drop(process_group_mut);
+
+    if let Some(foreground) = foreground {
+        foreground.broadcast_signal(KernelSignal::new(SIGHUP));
+    }
+}
@@ -0,0 +1,478 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Miscellaneous binary formats (binfmt_misc).
+//!
+//! The user space can register interpreters for executables that can be recognized by a magic
+//! number or a file name extension. When such an executable is executed, its interpreter is
+//! executed instead, with the path of the executable as an argument. This is how foreign
+//! binaries (e.g., binaries for other architectures) can be run transparently under emulators
+//! like QEMU user mode.
+//!
+//! The entries are managed via the files in `/proc/sys/fs/binfmt_misc`.
+//!
+//! Reference: <https://docs.kernel.org/admin-guide/binfmt-misc.html>
+
+use core::{
+    fmt::{self, Display, Formatter},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use ostd::task::Task;
+
+use crate::{
+    fs::vfs::path::{FsPath, Path, PathResolver},
+    prelude::*,
+};
+
+/// The number of bytes at the beginning of an executable that can be matched against a magic
+/// number.
+pub(super) const BINPRM_BUF_SIZE: usize = 256;
+
+/// The maximum length of a string that registers a new entry.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.16/source/fs/binfmt_misc.c#L47>
+pub const MAX_REGISTER_LEN: usize = 1920;
+
+/// The registered entries, where newer entries come first.
+static ENTRIES: Mutex<Vec<Arc<BinfmtEntry>>> = Mutex::new(Vec::new());
+
+/// Whether binfmt_misc is enabled as a whole.
+static IS_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// An entry that associates a binary format with its interpreter.
+pub struct BinfmtEntry {
+    name: String,
+    matcher: Matcher,
+    interpreter: String,
+    flags: BinfmtFlags,
+    /// The interpreter file opened at registration time, if the `F` flag is set.
+    interpreter_file: Option<Path>,
+    is_enabled: AtomicBool,
+}
+
+enum Matcher {
+    /// Matches the executables whose contents at `offset` are `magic` (after applying `mask`).
+    Magic {
+        offset: usize,
+        magic: Vec<u8>,
+        mask: Option<Vec<u8>>,
+    },
+    /// Matches the executables whose file names have the extension.
+    Extension(String),
+}
+
+bitflags! {
+    struct BinfmtFlags: u8 {
+        /// Preserves the original `argv[0]` (the `P` flag).
+        const PRESERVE_ARGV0 = 1 << 0;
+        /// Opens the interpreter when the entry is registered (the `F` flag).
+        const FIX_BINARY     = 1 << 1;
+    }
+}
+
+/// A command written to `/proc/sys/fs/binfmt_misc/status` or an entry file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinfmtCommand {
+    /// Disables the entry (or binfmt_misc as a whole), written as `0`.
+    Disable,
+    /// Enables the entry (or binfmt_misc as a whole), written as `1`.
+    Enable,
+    /// Removes the entry (or all entries), written as `-1`.
+    Remove,
+}
+
+impl BinfmtCommand {
+    /// Parses a command, with an optional trailing newline.
+    pub fn parse(command: &str) -> Result<Self> {
+        match command.strip_suffix('\n').unwrap_or(command) {
+            "0" => Ok(Self::Disable),
+            "1" => Ok(Self::Enable),
+            "-1" => Ok(Self::Remove),
+            _ => return_errno_with_message!(Errno::EINVAL, "the binfmt_misc command is invalid"),
+        }
+    }
+}
+
+impl BinfmtEntry {
+    /// Parses the string that registers a new entry.
+    ///
+    /// The string has the format of `:name:type:offset:magic:mask:interpreter:flags`, where the
+    /// first character (`:` here) can be any character that is used as the delimiter.
+    fn parse(line: &str) -> Result<Self> {
+        let line = line.strip_suffix('\n').unwrap_or(line);
+
+        let mut chars = line.chars();
+        let Some(delimiter) = chars.next() else {
+            return_errno_with_message!(Errno::EINVAL, "the registration string is empty");
+        };
+        let fields: Vec<&str> = chars.as_str().split(delimiter).collect();
+        let [name, type_, offset, magic, mask, interpreter, rest @ ..] = fields.as_slice() else {
+            return_errno_with_message!(Errno::EINVAL, "the registration string is incomplete");
+        };
+        let flags = match rest {
+            [] => "",
+            [flags] => *flags,
+            _ => return_errno_with_message!(
+                Errno::EINVAL,
+                "the registration string has too many fields"
+            ),
+        };
+
+        if name.is_empty() || *name == "." || *name == ".." || name.contains('/') {
+            return_errno_with_message!(Errno::EINVAL, "the entry name is invalid");
+        }
+
+        let matcher = match *type_ {
+            "M" => Matcher::parse_magic(offset, magic, mask)?,
+            "E" => Matcher::parse_extension(offset, magic, mask)?,
+            _ => return_errno_with_message!(Errno::EINVAL, "the entry type is invalid"),
+        };
+
+        if interpreter.is_empty() {
+            return_errno_with_message!(Errno::EINVAL, "the interpreter is empty");
+        }
+
+        let mut parsed_flags = BinfmtFlags::empty();
+        for flag in flags.chars() {
+            parsed_flags |= match flag {
+                'P' => BinfmtFlags::PRESERVE_ARGV0,
+                'F' => BinfmtFlags::FIX_BINARY,
+                'O' | 'C' => {
+                    return_errno_with_message!(
+                        Errno::EINVAL,
+                        "the credential-related flags are not supported"
+                    )
+                }
+                _ => return_errno_with_message!(Errno::EINVAL, "the entry flag is invalid"),
+            };
+        }
+
+        let interpreter_file = if parsed_flags.contains(BinfmtFlags::FIX_BINARY) {
+            let task = Task::current().unwrap();
+            let thread_local = task.as_thread_local().unwrap();
+            let fs_ref = thread_local.borrow_fs();
+            let path_resolver = fs_ref.resolver().read();
+            Some(path_resolver.lookup(&FsPath::try_from(*interpreter)?)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            name: name.to_string(),
+            matcher,
+            interpreter: interpreter.to_string(),
+            flags: parsed_flags,
+            interpreter_file,
+            is_enabled: AtomicBool::new(true),
+        })
+    }
+
+    /// Returns the name of the entry.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns whether the entry is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.is_enabled.load(Ordering::Relaxed)
+    }
+
+    /// Enables or disables the entry.
+    pub fn set_enabled(&self, is_enabled: bool) {
+        self.is_enabled.store(is_enabled, Ordering::Relaxed);
+    }
+
+    /// Returns the interpreter of the executables that match this entry.
+    pub(super) fn interpreter(&self, path_resolver: &PathResolver) -> Result<Path> {
+        if let Some(interpreter_file) = self.interpreter_file.as_ref() {
+            return Ok(interpreter_file.clone());
+        }
+
+        path_resolver.lookup(&FsPath::try_from(self.interpreter.as_str())?)
+    }
+
+    /// Returns the arguments for the interpreter, given the path and the arguments of the
+    /// executable.
+    pub(super) fn interpreter_argv(&self, file_name: CString, argv: Vec<CString>) -> Vec<CString> {
+        let mut new_argv = vec![CString::new(self.interpreter.as_str()).unwrap(), file_name];
+        if self.flags.contains(BinfmtFlags::PRESERVE_ARGV0) {
+            new_argv.extend(argv);
+        } else {
+            new_argv.extend(argv.into_iter().skip(1));
+        }
+        new_argv
+    }
+
+    fn matches(&self, file_first_page: &[u8], file_name: &str) -> bool {
+        match &self.matcher {
+            Matcher::Magic {
+                offset,
+                magic,
+                mask,
+            } => {
+                let Some(contents) = file_first_page.get(*offset..*offset + magic.len()) else {
+                    return false;
+                };
+                match mask {
+                    Some(mask) => contents
+                        .iter()
+                        .zip(mask.iter())
+                        .map(|(content, mask)| content & mask)
+                        .eq(magic.iter().copied()),
+                    None => contents == magic.as_slice(),
+                }
+            }
+            Matcher::Extension(extension) => file_name
+                .rsplit_once('.')
+                .is_some_and(|(_, file_extension)| file_extension == extension),
+        }
+    }
+}
+
+/// Formats the status of the entry, as shown in its file in `/proc/sys/fs/binfmt_misc`.
+impl Display for BinfmtEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.is_enabled() {
+            writeln!(f, "enabled")?;
+        } else {
+            writeln!(f, "disabled")?;
+        }
+        writeln!(f, "interpreter {}", self.interpreter)?;
+
+        write!(f, "flags: ")?;
+        if self.flags.contains(BinfmtFlags::PRESERVE_ARGV0) {
+            write!(f, "P")?;
+        }
+        if self.flags.contains(BinfmtFlags::FIX_BINARY) {
+            write!(f, "F")?;
+        }
+        writeln!(f)?;
+
+        match &self.matcher {
+            Matcher::Magic {
+                offset,
+                magic,
+                mask,
+            } => {
+                writeln!(f, "offset {}", offset)?;
+                writeln!(f, "magic {}", HexBytes(magic))?;
+                if let Some(mask) = mask {
+                    writeln!(f, "mask {}", HexBytes(mask))?;
+                }
+            }
+            Matcher::Extension(extension) => {
+                writeln!(f, "extension .{}", extension)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Matcher {
+    fn parse_magic(offset: &str, magic: &str, mask: &str) -> Result<Self> {
+        let offset = if offset.is_empty() {
+            0
+        } else {
+            offset
+                .parse::<usize>()
+                .map_err(|_| Error::with_message(Errno::EINVAL, "the offset is invalid"))?
+        };
+
+        let magic = unescape_hex(magic);
+        if magic.is_empty() {
+            return_errno_with_message!(Errno::EINVAL, "the magic is empty");
+        }
+        if offset
+            .checked_add(magic.len())
+            .is_none_or(|end| end > BINPRM_BUF_SIZE)
+        {
+            return_errno_with_message!(Errno::EINVAL, "the magic is out of the matched range");
+        }
+
+        let mask = if mask.is_empty() {
+            None
+        } else {
+            let mask = unescape_hex(mask);
+            if mask.len() != magic.len() {
+                return_errno_with_message!(
+                    Errno::EINVAL,
+                    "the mask and the magic have different lengths"
+                );
+            }
+            Some(mask)
+        };
+
+        // The magic is compared with the masked contents, so mask the magic in advance.
+        let magic = match mask.as_ref() {
+            Some(mask) => magic.iter().zip(mask.iter()).map(|(m, k)| m & k).collect(),
+            None => magic,
+        };
+
+        Ok(Self::Magic {
+            offset,
+            magic,
+            mask,
+        })
+    }
+
+    fn parse_extension(offset: &str, extension: &str, mask: &str) -> Result<Self> {
+        if !offset.is_empty() || !mask.is_empty() {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "the extension entry cannot have an offset or a mask"
+            );
+        }
+        if extension.is_empty() || extension.contains('/') {
+            return_errno_with_message!(Errno::EINVAL, "the extension is invalid");
+        }
+
+        Ok(Self::Extension(extension.to_string()))
+    }
+}
+
+/// Unescapes the `\xHH` sequences in a string.
+fn unescape_hex(escaped: &str) -> Vec<u8> {
+    let bytes = escaped.as_bytes();
+
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\'
+            && bytes.get(i + 1) == Some(&b'x')
+            && let Some(hex) = escaped.get(i + 2..i + 4)
+            && let Ok(byte) = u8::from_str_radix(hex, 16)
+        {
+            unescaped.push(byte);
+            i += 4;
+        } else {
+            unescaped.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    unescaped
+}
+
+struct HexBytes<'a>(&'a [u8]);
+
+impl Display for HexBytes<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for byte in self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// Registers a new entry with the registration string.
+pub fn register(line: &str) -> Result<()> {
+    let entry = BinfmtEntry::parse(line)?;
+
+    let mut entries = ENTRIES.lock();
+    if entries.iter().any(|e| e.name == entry.name) {
+        return_errno_with_message!(Errno::EEXIST, "the entry already exists");
+    }
+    entries.insert(0, Arc::new(entry));
+
+    Ok(())
+}
+
+/// Unregisters the entry with the name.
+pub fn unregister(name: &str) -> Result<()> {
+    let mut entries = ENTRIES.lock();
+    let Some(pos) = entries.iter().position(|e| e.name == name) else {
+        return_errno_with_message!(Errno::ENOENT, "the entry does not exist");
+    };
+    entries.remove(pos);
+
+    Ok(())
+}
+
+/// Unregisters all entries.
+pub fn unregister_all() {
+    ENTRIES.lock().clear();
+}
+
+/// Returns the entry with the name.
+pub fn lookup(name: &str) -> Option<Arc<BinfmtEntry>> {
+    ENTRIES.lock().iter().find(|e| e.name == name).cloned()
+}
+
+/// Returns all the registered entries.
+pub fn entries() -> Vec<Arc<BinfmtEntry>> {
+    ENTRIES.lock().clone()
+}
+
+/// Returns whether binfmt_misc is enabled as a whole.
+pub fn is_enabled() -> bool {
+    IS_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Enables or disables binfmt_misc as a whole.
+pub fn set_enabled(is_enabled: bool) {
+    IS_ENABLED.store(is_enabled, Ordering::Relaxed);
+}
+
+/// Finds the enabled entry that matches the executable.
+pub(super) fn find_entry(file_first_page: &[u8], file_name: &str) -> Option<Arc<BinfmtEntry>> {
+    if !is_enabled() {
+        return None;
+    }
+
+    ENTRIES
+        .lock()
+        .iter()
+        .find(|e| e.is_enabled() && e.matches(file_first_page, file_name))
+        .cloned()
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    #[ktest]
+    fn parse_magic_entry() {
+        let entry = BinfmtEntry::parse(":arm:M::\\x7fELF\\x01::/usr/bin/qemu-arm:P\n").unwrap();
+        assert_eq!(entry.name(), "arm");
+        assert!(entry.matches(b"\x7fELF\x01\x01", "/bin/true"));
+        assert!(!entry.matches(b"\x7fELF\x02\x01", "/bin/true"));
+        assert!(!entry.matches(b"\x7fELF", "/bin/true"));
+        assert_eq!(
+            entry.to_string(),
+            "enabled\ninterpreter /usr/bin/qemu-arm\nflags: P\noffset 0\nmagic 7f454c4601\n"
+        );
+
+        let entry = BinfmtEntry::parse("|masked|M|1|\\x10|\\xf0|/bin/sh|").unwrap();
+        assert!(entry.matches(b"\x00\x1f", "/bin/true"));
+        assert!(!entry.matches(b"\x1f\x00", "/bin/true"));
+    }
+
+    #[ktest]
+    fn parse_extension_entry() {
+        let entry = BinfmtEntry::parse(":py:E::py::/usr/bin/python3:").unwrap();
+        assert!(entry.matches(b"", "/tmp/script.py"));
+        assert!(!entry.matches(b"", "/tmp/script.pyc"));
+        assert!(!entry.matches(b"", "/tmp/script"));
+    }
+
+    #[ktest]
+    fn parse_invalid_entries() {
+        for line in [
+            "",
+            ":name:M::\\x7f:",
+            ":name:X::\\x7f::/bin/sh:",
+            ":../x:M::\\x7f::/bin/sh:",
+            ":name:M::::/bin/sh:",
+            ":name:M::\\x7f\\x45:\\xff:/bin/sh:",
+            ":name:M:256:\\x7f::/bin/sh:",
+            ":name:E:1:py::/bin/sh:",
+            ":name:M::\\x7f:::",
+            ":name:M::\\x7f::/bin/sh:Z",
+        ] {
+            assert!(BinfmtEntry::parse(line).is_err(), "{}", line);
+        }
+    }
+}
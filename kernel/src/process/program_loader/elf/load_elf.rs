@@ -12,8 +12,9 @@ use crate::{
     fs::vfs::path::{FsPath, Path, PathResolver},
     prelude::*,
     process::{
+        execve::is_set_id_ignored,
         process_vm::{AuxKey, AuxVec},
-        program_loader::check_executable_inode,
+        program_loader::check_executable_file,
     },
     util::random::getrandom,
     vm::{
@@ -112,8 +113,8 @@ fn lookup_and_parse_ldso(
     };
 
     let ldso_elf = {
+        check_executable_file(&ldso_file)?;
         let inode = ldso_file.inode();
-        check_executable_inode(inode.as_ref())?;
 
         let mut buf = Box::new([0u8; PAGE_SIZE]);
         let len = inode.read_bytes_at(0, &mut *buf)?;
@@ -154,7 +155,7 @@ fn map_vmos_and_build_aux_vec(
 
     // Set AT_SECURE based on setuid/setgid bits of the executable file.
     let mode = elf_file.inode().mode()?;
-    let secure = if mode.has_set_uid() || mode.has_set_gid() {
+    let secure = if (mode.has_set_uid() || mode.has_set_gid()) && !is_set_id_ignored(elf_file) {
         1
     } else {
         0
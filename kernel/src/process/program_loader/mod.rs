@@ -1,5 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
+pub mod binfmt_misc;
 pub(super) mod elf;
 mod shebang;
 
@@ -10,12 +11,10 @@ use self::{
 use crate::{
     fs::{
         file::{InodeType, Permission},
-        vfs::{
-            inode::Inode,
-            path::{FsPath, Path, PathResolver},
-        },
+        vfs::path::{FsPath, Path, PathResolver, PerMountFlags},
     },
     prelude::*,
+    security::lsm,
     vm::vmar::Vmar,
 };
 
@@ -31,20 +30,20 @@ pub(super) struct ProgramToLoad {
 }
 
 impl ProgramToLoad {
-    /// Constructs a new `ProgramToLoad` from a file and handles shebang interpretation if
-    /// necessary.
+    /// Constructs a new `ProgramToLoad` from a file and handles binfmt_misc and shebang
+    /// interpretation if necessary.
     pub(super) fn build_from_file(
         mut elf_file: Path,
         path_resolver: &PathResolver,
         mut argv: Vec<CString>,
         envp: Vec<CString>,
     ) -> Result<Self> {
-        check_executable_inode(elf_file.inode().as_ref())?;
+        check_executable_file(&elf_file)?;
 
-        // A limit to the recursion depth of shebang executables.
+        // A limit to the recursion depth of interpreted executables.
         //
-        // If the interpreter is a shebang, then recursion will be triggered. If it loops, we
-        // should fail. We follow the same limit as Linux.
+        // If the interpreter is a shebang or another interpreted executable, then recursion will
+        // be triggered. If it loops, we should fail. We follow the same limit as Linux.
         let mut recursive_limit = 5;
 
         let (file_first_page, len) = loop {
@@ -55,24 +54,39 @@ impl ProgramToLoad {
                 (buffer, len)
             };
 
-            let Some(mut new_argv) = parse_shebang_line(&file_first_page[..len])? else {
-                break (file_first_page, len);
-            };
+            // The path of the executable, which is passed to the interpreter as an argument.
+            //
+            // Note that Linux passes the path specified by the user, which may be relative.
+            let file_name = path_resolver.make_abs_path(&elf_file).into_string();
+
+            // Like Linux, binfmt_misc takes precedence over shebangs.
+            let (interpreter, new_argv) =
+                if let Some(entry) = binfmt_misc::find_entry(&file_first_page[..len], &file_name) {
+                    let interpreter = entry.interpreter(path_resolver)?;
+                    let new_argv = entry.interpreter_argv(CString::new(file_name)?, argv);
+                    (interpreter, new_argv)
+                } else if let Some(mut new_argv) = parse_shebang_line(&file_first_page[..len])? {
+                    let interpreter = {
+                        let filename = new_argv[0].to_str()?.to_string();
+                        let fs_path = FsPath::try_from(filename.as_str())?;
+                        path_resolver.lookup(&fs_path)?
+                    };
+                    // The original `argv[0]` is replaced by the path of the script.
+                    new_argv.push(CString::new(file_name)?);
+                    new_argv.extend(argv.into_iter().skip(1));
+                    (interpreter, new_argv)
+                } else {
+                    break (file_first_page, len);
+                };
 
             if recursive_limit == 0 {
                 return_errno_with_message!(Errno::ELOOP, "the recursieve limit is reached");
             }
             recursive_limit -= 1;
 
-            let interpreter = {
-                let filename = new_argv[0].to_str()?.to_string();
-                let fs_path = FsPath::try_from(filename.as_str())?;
-                path_resolver.lookup(&fs_path)?
-            };
-            check_executable_inode(interpreter.inode().as_ref())?;
+            check_executable_file(&interpreter)?;
 
             // Update the argument list and the executable inode. Then, try again.
-            new_argv.extend(argv);
             argv = new_argv;
             elf_file = interpreter;
         };
@@ -108,7 +122,8 @@ impl ProgramToLoad {
     }
 }
 
-fn check_executable_inode(inode: &dyn Inode) -> Result<()> {
+fn check_executable_file(file: &Path) -> Result<()> {
+    let inode = file.inode();
     if inode.type_().is_directory() {
         return_errno_with_message!(Errno::EISDIR, "the inode is a directory");
     }
@@ -121,9 +136,13 @@ fn check_executable_inode(inode: &dyn Inode) -> Result<()> {
         return_errno_with_message!(Errno::EACCES, "the inode is not a regular file");
     }
 
-    if inode.check_permission(Permission::MAY_EXEC).is_err() {
+    if file.mount_flags().contains(PerMountFlags::NOEXEC) {
+        return_errno_with_message!(Errno::EACCES, "the mount disallows program execution");
+    }
+
+    if file.check_permission(Permission::MAY_EXEC).is_err() {
         return_errno_with_message!(Errno::EACCES, "the inode is not executable");
     }
 
-    Ok(())
+    lsm::bprm_check(file)
 }
@@ -1,54 +1,69 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use super::binfmt_misc::BINPRM_BUF_SIZE;
 use crate::prelude::*;
 
 /// Tries to parse a buffer as a shebang line.
 ///
 /// If the buffer starts with `#!` and its header is a valid shebang sequence,
 /// then the function returns `Ok(Some(parts))`, where `parts` is a `Vec` that
-/// contains the path of and the arguments for the interpreter.
+/// contains the path of and the optional argument for the interpreter.
+///
+/// Like Linux, everything after the interpreter path (with the leading and trailing
+/// whitespaces removed) is passed as a single argument, and only the first
+/// [`BINPRM_BUF_SIZE`] bytes of the file are examined. If the shebang line is longer
+/// than that, the argument will be truncated, but the interpreter path must not be.
 ///
 /// If the buffer starts with `#!` but some error occurs while parsing the
 /// file, then `Err(_)` is returned. If the buffer does not start with `#!`,
 /// then `Ok(None)` is returned.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.16/source/fs/binfmt_script.c>
 pub fn parse_shebang_line(file_first_page: &[u8]) -> Result<Option<Vec<CString>>> {
     if !file_first_page.starts_with(b"#!") {
         // The file is not a shebang.
         return Ok(None);
     }
 
-    let Some(first_line_len) = file_first_page.iter().position(|&c| c == b'\n') else {
-        return_errno_with_message!(Errno::ENOEXEC, "the shebang line is too long");
-    };
+    let buf = &file_first_page[..file_first_page.len().min(BINPRM_BUF_SIZE)];
+    // The shebang line ends at the first newline, or at the first nul terminator.
+    let line_end = buf.iter().position(|&c| c == b'\n' || c == b'\0');
+    let is_truncated = line_end.is_none() && file_first_page.len() > BINPRM_BUF_SIZE;
 
     // Skip `#!`.
-    let shebang_header = &file_first_page[2..first_line_len];
-    let mut shebang_argv = Vec::new();
-    for arg in shebang_header.split(|&c| c == b' ' || c == b'\t') {
-        if arg.is_empty() {
-            continue;
-        }
-
-        let arg = CString::new(arg).map_err(|_| {
-            Error::with_message(Errno::ENOEXEC, "unexpected nul terminator is found")
-        })?;
-        shebang_argv.push(arg);
-    }
+    let shebang_header = &buf[2..line_end.unwrap_or(buf.len())];
+    let shebang_header = shebang_header.trim_ascii_start();
 
-    if shebang_argv.is_empty() {
+    let interpreter_len = shebang_header
+        .iter()
+        .position(|&c| c == b' ' || c == b'\t')
+        .unwrap_or(shebang_header.len());
+    if interpreter_len == 0 {
         return_errno_with_message!(Errno::ENOEXEC, "no interpreter program is found");
     }
+    if is_truncated && interpreter_len == shebang_header.len() {
+        return_errno_with_message!(Errno::ENOEXEC, "the interpreter path is too long");
+    }
+
+    let (interpreter, arg) = shebang_header.split_at(interpreter_len);
+    let arg = arg.trim_ascii();
+
+    // There will be no nul terminators since the line ends at the first one.
+    let mut shebang_argv = vec![CString::new(interpreter).unwrap()];
+    if !arg.is_empty() {
+        shebang_argv.push(CString::new(arg).unwrap());
+    }
 
     Ok(Some(shebang_argv))
 }
 
 #[cfg(ktest)]
 mod test {
-    use alloc::{ffi::CString, vec};
+    use alloc::{ffi::CString, string::String, vec};
 
     use ostd::prelude::*;
 
-    use super::parse_shebang_line;
+    use super::{BINPRM_BUF_SIZE, parse_shebang_line};
 
     #[ktest]
     fn parse_shebang_line_with_multiple_args() {
@@ -72,4 +87,41 @@ mod test {
             ]
         );
     }
+
+    #[ktest]
+    fn parse_shebang_line_with_single_arg() {
+        const LINE: &str = "#!/usr/bin/env -S python3 -u\n";
+        let res = parse_shebang_line(LINE.as_bytes()).unwrap().unwrap();
+        assert_eq!(
+            res,
+            vec![
+                CString::new("/usr/bin/env").unwrap(),
+                CString::new("-S python3 -u").unwrap()
+            ]
+        );
+
+        const LINE_WITHOUT_NEWLINE: &str = "#!/bin/sh";
+        let res = parse_shebang_line(LINE_WITHOUT_NEWLINE.as_bytes())
+            .unwrap()
+            .unwrap();
+        assert_eq!(res, vec![CString::new("/bin/sh").unwrap()]);
+    }
+
+    #[ktest]
+    fn parse_shebang_line_too_long() {
+        let mut line = String::from("#!/bin/sh ");
+        line.extend(core::iter::repeat_n('x', 300));
+        let res = parse_shebang_line(line.as_bytes()).unwrap().unwrap();
+        assert_eq!(res[0], CString::new("/bin/sh").unwrap());
+        assert_eq!(
+            res[1].as_bytes().len(),
+            BINPRM_BUF_SIZE - "#!/bin/sh ".len()
+        );
+
+        let mut line = String::from("#!/");
+        line.extend(core::iter::repeat_n('x', 300));
+        assert!(parse_shebang_line(line.as_bytes()).is_err());
+
+        assert!(parse_shebang_line(b"#!  \n").is_err());
+    }
 }
@@ -89,6 +89,11 @@ pub fn handle_pending_signal(
     match sig_action {
         SigAction::Ign => {
             trace!("Ignore signal {:?}", sig_num);
+
+            // No signal handler is invoked, so the system call can be restarted transparently.
+            if let Some(pre_syscall_ret) = syscall_restart {
+                restart_syscall(user_ctx, pre_syscall_ret);
+            }
         }
         SigAction::User {
             handler_addr,
@@ -99,16 +104,7 @@ pub fn handle_pending_signal(
             if let Some(pre_syscall_ret) = syscall_restart
                 && flags.contains(SigActionFlags::SA_RESTART)
             {
-                #[cfg(target_arch = "x86_64")]
-                const SYSCALL_INSTR_LEN: usize = 2; // syscall
-                #[cfg(target_arch = "riscv64")]
-                const SYSCALL_INSTR_LEN: usize = 4; // ecall
-                #[cfg(target_arch = "loongarch64")]
-                const SYSCALL_INSTR_LEN: usize = 4; // syscall
-
-                user_ctx.set_syscall_ret(pre_syscall_ret);
-                user_ctx
-                    .set_instruction_pointer(user_ctx.instruction_pointer() - SYSCALL_INSTR_LEN);
+                restart_syscall(user_ctx, pre_syscall_ret);
             }
 
             if let Err(e) = handle_user_signal(
@@ -132,6 +128,12 @@ pub fn handle_pending_signal(
             let sig_default_action = SigDefaultAction::from_signum(sig_num);
             trace!("sig_default_action = {:?}", sig_default_action);
 
+            // No signal handler is invoked, so the system call can be restarted transparently
+            // (e.g., after the process is stopped and continued), unless the process exits.
+            if let Some(pre_syscall_ret) = syscall_restart {
+                restart_syscall(user_ctx, pre_syscall_ret);
+            }
+
             match sig_default_action {
                 SigDefaultAction::Core | SigDefaultAction::Term => {
                     warn!(
@@ -150,6 +152,19 @@ pub fn handle_pending_signal(
     }
 }
 
+/// Restarts the interrupted system call once the control returns to the userspace.
+fn restart_syscall(user_ctx: &mut UserContext, pre_syscall_ret: usize) {
+    #[cfg(target_arch = "x86_64")]
+    const SYSCALL_INSTR_LEN: usize = 2; // syscall
+    #[cfg(target_arch = "riscv64")]
+    const SYSCALL_INSTR_LEN: usize = 4; // ecall
+    #[cfg(target_arch = "loongarch64")]
+    const SYSCALL_INSTR_LEN: usize = 4; // syscall
+
+    user_ctx.set_syscall_ret(pre_syscall_ret);
+    user_ctx.set_instruction_pointer(user_ctx.instruction_pointer() - SYSCALL_INSTR_LEN);
+}
+
 /// A guard that restores the signal mask on drop.
 struct RestoreSigMaskGuard<'a> {
     ctx: &'a Context<'a>,
@@ -0,0 +1,20 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Exposes the internal states of the scheduler in debugfs (i.e., `/sys/kernel/debug/sched`).
+
+use super::nr_queued_and_running;
+use crate::{
+    fs::debugfs::{self, DebugDir, DebugFile},
+    prelude::*,
+};
+
+pub(super) fn init_in_first_kthread() {
+    let files = vec![
+        DebugFile::new("nr_queued", || format!("{}\n", nr_queued_and_running().0)),
+        DebugFile::new("nr_running", || format!("{}\n", nr_queued_and_running().1)),
+    ];
+
+    if let Err(err) = debugfs::register_dir(DebugDir::new("sched", files)) {
+        warn!("failed to register the scheduler in debugfs: {:?}", err);
+    }
+}
@@ -1,5 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
+mod debugfs;
 mod nice;
 mod sched_class;
 mod stats;
@@ -7,7 +8,13 @@ mod stats;
 pub use self::{
     nice::{AtomicNice, Nice},
     sched_class::{
-        RealTimePolicy, RealTimePriority, SchedAttr, SchedPolicy, init, init_on_each_cpu,
+        CpuIdleType, CpuSchedStats, LoadBalanceStats, RealTimePolicy, RealTimePriority, SchedAttr,
+        SchedPolicy, TaskSchedStats, cpu_busy_time, cpu_sched_stats, idle_balance, init,
+        init_on_each_cpu, migrate_tasks_from, set_cpu_affinity, set_cpu_online,
     },
     stats::{loadavg, nr_queued_and_running},
 };
+
+pub fn init_in_first_kthread() {
+    debugfs::init_in_first_kthread();
+}
@@ -0,0 +1,304 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Load balancing among the per-CPU run queues.
+//!
+//! Every CPU schedules the tasks in its own run queue, so the tasks may pile up on some CPUs
+//! while the other CPUs are idle. The load is balanced in the following ways:
+//!
+//! - **Wake-up placement.** A task is placed on a suitable CPU when it is spawned or woken up.
+//!   See [`ClassScheduler::select_cpu`].
+//! - **Periodic balancing.** Every [`BALANCE_INTERVAL_TICKS`] ticks, a CPU pulls fair tasks from
+//!   the busiest CPU if the busiest CPU has more tasks waiting. If the CPU cannot pull any tasks
+//!   but has tasks waiting, it kicks an idle CPU, whose tick is stopped, to steal them.
+//! - **Idle balancing.** A CPU that is about to become idle steals fair tasks from the busiest
+//!   CPU. See [`idle_balance`].
+//!
+//! Tasks of all classes except the stop and idle classes are moved off the CPUs that are excluded
+//! from their affinity masks, either periodically or when the CPUs become idle. See
+//! [`set_cpu_affinity`].
+//!
+//! Real-time tasks are not moved for balancing, since their order is decided by priorities rather
+//! than loads.
+//!
+//! Like Linux without scheduling domains, all the CPUs are considered to share the same cache, so
+//! moving a task between any two CPUs has the same cost.
+//!
+//! [`set_cpu_affinity`]: super::set_cpu_affinity
+
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use ostd::{
+    cpu::{AtomicCpuSet, CpuId, CpuSet, PinCurrentCpu, all_cpus},
+    irq::disable_local,
+    sync::{LocalIrqDisabled, SpinLockGuard},
+    task::{
+        Task,
+        scheduler::{EnqueueFlags, Scheduler},
+    },
+    timer::Jiffies,
+    util::id_set::Id,
+};
+
+use super::{ClassScheduler, PerCpuClassRqSet, PerCpuLoadStats, SCHEDULER, schedstat::CpuIdleType};
+use crate::{cpu::hotplug, thread::AsThread};
+
+/// The number of ticks between two rounds of periodic balancing on a CPU.
+const BALANCE_INTERVAL_TICKS: u64 = 4;
+
+/// Lock-free snapshots of the loads of the CPUs.
+///
+/// The snapshots are updated whenever a run queue is changed with its lock held. They may be
+/// stale when they are read, so they only serve as hints to find busy or idle CPUs without
+/// locking every run queue.
+pub(super) struct LoadHints {
+    queue_lens: Box<[AtomicU32]>,
+    idle_cpus: AtomicCpuSet,
+}
+
+impl LoadHints {
+    pub(super) fn new() -> Self {
+        Self {
+            queue_lens: all_cpus().map(|_| AtomicU32::new(0)).collect(),
+            idle_cpus: AtomicCpuSet::new(CpuSet::new_full()),
+        }
+    }
+
+    /// Updates the snapshot of the CPU's load.
+    pub(super) fn update(&self, cpu: CpuId, rq: &PerCpuClassRqSet) {
+        let PerCpuLoadStats { queue_len, is_idle } = rq.load_stats();
+        self.queue_lens[cpu.as_usize()].store(queue_len, Ordering::Relaxed);
+
+        // Avoid bouncing the cache line of the shared set if nothing is changed.
+        if self.idle_cpus.contains(cpu, Ordering::Relaxed) != is_idle {
+            if is_idle {
+                self.idle_cpus.add(cpu, Ordering::Relaxed);
+            } else {
+                self.idle_cpus.remove(cpu, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Returns the number of tasks waiting in the CPU's run queue.
+    pub(super) fn queue_len(&self, cpu: CpuId) -> u32 {
+        self.queue_lens[cpu.as_usize()].load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of tasks waiting or running on the CPU.
+    pub(super) fn load(&self, cpu: CpuId) -> u32 {
+        self.queue_len(cpu) + u32::from(!self.idle_cpus.contains(cpu, Ordering::Relaxed))
+    }
+
+    /// Returns whether the CPU is running its idle task with no other tasks waiting.
+    pub(super) fn is_idle(&self, cpu: CpuId) -> bool {
+        self.idle_cpus.contains(cpu, Ordering::Relaxed) && self.queue_len(cpu) == 0
+    }
+}
+
+/// Steals tasks for the current CPU, which is about to become idle.
+///
+/// Returns whether the current CPU has tasks to run.
+pub fn idle_balance() -> bool {
+    let scheduler = SCHEDULER.get().unwrap();
+
+    let guard = disable_local();
+    let cpu = guard.current_cpu();
+    if !hotplug::is_cpu_online(cpu) {
+        return false;
+    }
+    scheduler.push_disallowed_tasks(cpu);
+    scheduler.pull_tasks(cpu, CpuIdleType::NewlyIdle);
+
+    scheduler.load_hints.queue_len(cpu) > 0
+}
+
+pub(super) fn balance_on_tick() {
+    let scheduler = SCHEDULER.get().unwrap();
+
+    let guard = disable_local();
+    let cpu = guard.current_cpu();
+
+    scheduler.push_disallowed_tasks(cpu);
+
+    // Stagger the CPUs so that they do not lock the run queues at the same time.
+    let jiffies = Jiffies::elapsed().as_u64();
+    if (jiffies + cpu.as_usize() as u64) % BALANCE_INTERVAL_TICKS != 0 {
+        return;
+    }
+
+    let idle_type = if scheduler.load_hints.is_idle(cpu) {
+        CpuIdleType::Idle
+    } else {
+        CpuIdleType::NotIdle
+    };
+    if scheduler.pull_tasks(cpu, idle_type) == 0 {
+        scheduler.kick_idle_cpu(cpu);
+    }
+}
+
+impl ClassScheduler {
+    /// Pulls fair tasks from the busiest CPU to the CPU.
+    ///
+    /// Returns the number of pulled tasks.
+    fn pull_tasks(&self, cpu: CpuId, idle_type: CpuIdleType) -> usize {
+        let Some(busiest) = self.find_busiest_cpu(cpu) else {
+            let mut rq = self.rqs[cpu.as_usize()].lock();
+            let lb_stats = rq.stats.lb_mut(idle_type);
+            lb_stats.count += 1;
+            lb_stats.no_busy_queue += 1;
+            lb_stats.balanced += 1;
+            return 0;
+        };
+
+        let (mut rq, mut busiest_rq) = self.lock_rq_pair(cpu, busiest);
+        if !rq.is_online || !busiest_rq.is_online {
+            return 0;
+        }
+        rq.stats.lb_mut(idle_type).count += 1;
+
+        // Recheck the loads with the locks held.
+        let queue_len = rq.load_stats().queue_len as usize;
+        let busiest_len = busiest_rq.load_stats().queue_len as usize;
+        let nr_to_pull = if idle_type != CpuIdleType::NotIdle && queue_len == 0 {
+            busiest_len.div_ceil(2)
+        } else {
+            busiest_len.saturating_sub(queue_len) / 2
+        };
+        if nr_to_pull == 0 {
+            rq.stats.lb_mut(idle_type).balanced += 1;
+            return 0;
+        }
+
+        let prev_task_addr = busiest_rq.prev_task_addr;
+        let mut nr_pulled = 0;
+        busiest_rq.fair.detach_if(
+            nr_to_pull,
+            &mut |task| can_migrate(task, cpu, prev_task_addr),
+            &mut |task| {
+                task.cpu().set_anyway(cpu);
+                task.as_thread().unwrap().sched_attr().set_last_cpu(cpu);
+                rq.fair.attach(task);
+                nr_pulled += 1;
+            },
+        );
+
+        let lb_stats = rq.stats.lb_mut(idle_type);
+        lb_stats.imbalance += nr_to_pull as u64;
+        lb_stats.gained += nr_pulled as u64;
+        if nr_pulled == 0 {
+            lb_stats.failed += 1;
+        }
+
+        self.load_hints.update(busiest, &busiest_rq);
+        self.load_hints.update(cpu, &rq);
+
+        nr_pulled
+    }
+
+    /// Finds the CPU with the most waiting tasks other than the given CPU.
+    fn find_busiest_cpu(&self, cpu: CpuId) -> Option<CpuId> {
+        hotplug::online_cpus()
+            .iter()
+            .filter(|busiest| *busiest != cpu)
+            .map(|busiest| (busiest, self.load_hints.queue_len(busiest)))
+            .filter(|(_, queue_len)| *queue_len > 0)
+            .max_by_key(|(_, queue_len)| *queue_len)
+            .map(|(busiest, _)| busiest)
+    }
+
+    /// Wakes up an idle CPU to steal the tasks waiting on the CPU.
+    ///
+    /// The tick of an idle CPU is stopped, so it does not balance the load periodically.
+    fn kick_idle_cpu(&self, cpu: CpuId) {
+        if self.load_hints.queue_len(cpu) == 0 {
+            return;
+        }
+
+        let online_cpus = hotplug::online_cpus();
+        let Some(idle_cpu) =
+            Self::cycle_after(cpu, &online_cpus).find(|cpu| self.load_hints.is_idle(*cpu))
+        else {
+            return;
+        };
+
+        // The IPI does nothing but brings the idle CPU out of its halted state. Then it runs
+        // `idle_balance` in its idle loop.
+        ostd::smp::inter_processor_call(&CpuSet::from(idle_cpu), || {});
+    }
+
+    /// Moves the waiting tasks that are not allowed to run on the CPU to other CPUs.
+    fn push_disallowed_tasks(&self, cpu: CpuId) {
+        let tasks = {
+            let mut rq = self.rqs[cpu.as_usize()].lock();
+            if !rq.has_disallowed_tasks {
+                return;
+            }
+            rq.has_disallowed_tasks = false;
+
+            let mut tasks = Vec::new();
+            let mut is_disallowed = |task: &Arc<Task>| !is_allowed_on(task, cpu);
+            let mut detach = |task: Arc<Task>| {
+                task.cpu().set_to_none();
+                tasks.push(task);
+            };
+            rq.real_time
+                .detach_if(usize::MAX, &mut is_disallowed, &mut detach);
+            rq.fair
+                .detach_if(usize::MAX, &mut is_disallowed, &mut detach);
+
+            self.load_hints.update(cpu, &rq);
+            tasks
+        };
+
+        for task in tasks {
+            if let Some(target_cpu) = self.enqueue(task, EnqueueFlags::Wake) {
+                // Wake up the target CPU in case it is halted, so that it picks the task soon.
+                ostd::smp::inter_processor_call(&CpuSet::from(target_cpu), || {});
+            }
+        }
+    }
+
+    /// Locks the run queues of two different CPUs in a fixed order to avoid deadlocks.
+    fn lock_rq_pair(
+        &self,
+        cpu: CpuId,
+        other: CpuId,
+    ) -> (
+        SpinLockGuard<'_, PerCpuClassRqSet, LocalIrqDisabled>,
+        SpinLockGuard<'_, PerCpuClassRqSet, LocalIrqDisabled>,
+    ) {
+        debug_assert_ne!(cpu, other);
+
+        if cpu.as_usize() < other.as_usize() {
+            let rq = self.rqs[cpu.as_usize()].lock();
+            let other_rq = self.rqs[other.as_usize()].lock();
+            (rq, other_rq)
+        } else {
+            let other_rq = self.rqs[other.as_usize()].lock();
+            let rq = self.rqs[cpu.as_usize()].lock();
+            (rq, other_rq)
+        }
+    }
+}
+
+/// Returns whether the task is allowed to run on the CPU by its affinity mask.
+///
+/// If all the CPUs in the affinity mask have been taken offline, the task is allowed to run on
+/// any CPU. See [`ClassScheduler::select_cpu`].
+pub(super) fn is_allowed_on(task: &Task, cpu: CpuId) -> bool {
+    let Some(thread) = task.as_thread() else {
+        return true;
+    };
+
+    let affinity = thread.atomic_cpu_affinity();
+    affinity.contains(cpu, Ordering::Relaxed)
+        || !(affinity.load(Ordering::Relaxed).iter()).any(hotplug::is_cpu_online)
+}
+
+/// Returns whether the waiting task can be migrated to the CPU.
+///
+/// The task that is most recently switched out on its CPU is not migrated. It is cache-hot, and
+/// the context switch may not have completed yet.
+fn can_migrate(task: &Arc<Task>, cpu: CpuId, prev_task_addr: usize) -> bool {
+    Arc::as_ptr(task) as usize != prev_task_addr && is_allowed_on(task, cpu)
+}
@@ -3,6 +3,7 @@
 use alloc::{collections::BinaryHeap, sync::Arc};
 use core::{
     cmp::{self, Reverse},
+    mem,
     sync::atomic::{AtomicU64, Ordering},
 };
 
@@ -351,4 +352,51 @@ impl SchedClassRq for FairClassRq {
             UpdateFlags::Exit => !self.is_empty(),
         }
     }
+
+    fn detach_if(
+        &mut self,
+        max: usize,
+        filter: &mut dyn FnMut(&Arc<Task>) -> bool,
+        detach: &mut dyn FnMut(Arc<Task>),
+    ) {
+        if max == 0 || self.entities.is_empty() {
+            return;
+        }
+
+        let mut entities = mem::take(&mut self.entities).into_vec();
+        // Consider the threads with the largest vruntimes first, which are the last to run.
+        if max < entities.len() {
+            entities.sort_unstable();
+        }
+
+        let mut nr_detached = 0;
+        let detached = entities.extract_if(.., |Reverse(FairQueueItem(entity, _))| {
+            let should_detach = nr_detached < max && filter(entity);
+            nr_detached += usize::from(should_detach);
+            should_detach
+        });
+        for Reverse(FairQueueItem(entity, vruntime)) in detached {
+            let fair_attr = &entity.as_thread().unwrap().sched_attr().fair;
+            let (old_weight, _weight) = fair_attr.fetch_weight();
+            self.total_weight -= old_weight;
+
+            // The minimum vruntimes of different run queues are unrelated, so only the lag
+            // behind the minimum vruntime is kept. See `attach` for the other half.
+            fair_attr.vruntime.store(
+                vruntime.saturating_sub(self.min_vruntime),
+                Ordering::Relaxed,
+            );
+            detach(entity);
+        }
+
+        self.entities = BinaryHeap::from(entities);
+    }
+
+    fn attach(&mut self, entity: Arc<Task>) {
+        let fair_attr = &entity.as_thread().unwrap().sched_attr().fair;
+        fair_attr
+            .vruntime
+            .fetch_add(self.min_vruntime, Ordering::Relaxed);
+        self.enqueue(entity, None);
+    }
 }
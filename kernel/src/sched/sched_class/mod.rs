@@ -4,7 +4,7 @@
 
 #![warn(unused)]
 
-use alloc::{boxed::Box, sync::Arc};
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
 use core::{fmt, ops::Bound, sync::atomic::Ordering};
 
 use ostd::{
@@ -19,16 +19,28 @@ use ostd::{
             info::CommonSchedInfo, inject_scheduler,
         },
     },
+    timer,
     util::id_set::Id,
 };
+use spin::Once;
 
 use super::{
     nice::Nice,
     stats::{SchedulerStats, set_stats_from_scheduler},
 };
-use crate::thread::{AsThread, Thread};
+use crate::{
+    cpu::hotplug,
+    thread::{AsThread, Thread},
+    trace::{
+        current_tid,
+        events::sched::{SCHED_SWITCH, trace_sched_switch},
+        task_tid,
+    },
+};
 
+mod balance;
 mod policy;
+mod schedstat;
 mod time;
 
 mod fair;
@@ -36,16 +48,24 @@ mod idle;
 mod real_time;
 mod stop;
 
-use self::policy::{SchedPolicyKind, SchedPolicyState};
+use self::{
+    balance::LoadHints,
+    policy::{SchedPolicyKind, SchedPolicyState},
+};
 pub use self::{
+    balance::idle_balance,
     policy::SchedPolicy,
     real_time::{RealTimePolicy, RealTimePriority},
+    schedstat::{CpuIdleType, CpuSchedStats, LoadBalanceStats, TaskSchedStats},
 };
 
 type SchedEntity = (Arc<Task>, Arc<Thread>);
 
+static SCHEDULER: Once<&'static ClassScheduler> = Once::new();
+
 pub fn init() {
     let scheduler = Box::leak(Box::new(ClassScheduler::new()));
+    SCHEDULER.call_once(|| scheduler);
 
     // Inject the scheduler into the ostd for actual scheduling work.
     inject_scheduler(scheduler);
@@ -57,9 +77,103 @@ pub fn init() {
 }
 
 pub fn init_on_each_cpu() {
+    // Balance the load before the tick of the scheduler, so that the tasks pulled to an idle CPU
+    // can preempt its idle task in the same tick.
+    timer::register_callback_on_cpu(balance::balance_on_tick);
     enable_preemption_on_cpu();
 }
 
+/// Sets the CPU affinity mask of the thread.
+///
+/// If the thread is running or waiting on a CPU that is excluded from the mask, it is migrated to
+/// an allowed CPU within a few ticks. If the thread is the current thread, it is migrated before
+/// this function returns.
+pub fn set_cpu_affinity(thread: &Thread, affinity: &CpuSet) {
+    thread
+        .atomic_cpu_affinity()
+        .store(affinity, Ordering::Relaxed);
+
+    // The thread may be on any of the disallowed CPUs. A running thread will be preempted by the
+    // tick (see `update_current`), so only the waiting threads need to be checked.
+    let scheduler = SCHEDULER.get().unwrap();
+    for cpu in all_cpus().filter(|cpu| !affinity.contains(*cpu)) {
+        scheduler.rqs[cpu.as_usize()].lock().has_disallowed_tasks = true;
+    }
+
+    let Some(current_task) = Task::current() else {
+        return;
+    };
+    if !current_task
+        .as_thread()
+        .is_some_and(|current| core::ptr::eq(current.as_ref(), thread))
+    {
+        return;
+    }
+    // Yielding switches the disallowed CPU to its idle task, which pushes the current thread to
+    // an allowed CPU before running anything else. So the thread does not run again until it is
+    // migrated. The loop only repeats if the mask is changed again in the meantime.
+    while !balance::is_allowed_on(&current_task, CpuId::current_racy()) {
+        Thread::yield_now();
+    }
+}
+
+/// Marks the CPU as online or offline.
+///
+/// An offline CPU runs its idle task only. The current task on the CPU is preempted at the next
+/// tick, after which the tasks on the CPU can be migrated away with [`migrate_tasks_from`].
+pub fn set_cpu_online(cpu: CpuId, is_online: bool) {
+    let scheduler = SCHEDULER.get().unwrap();
+    scheduler.rqs[cpu.as_usize()].lock().is_online = is_online;
+}
+
+/// Migrates the runnable tasks on the offline CPU to the online CPUs.
+///
+/// Returns whether the CPU is running its idle task only. Otherwise, the current task on the
+/// CPU has not been preempted yet, and this function should be called again later.
+pub fn migrate_tasks_from(cpu: CpuId) -> bool {
+    let scheduler = SCHEDULER.get().unwrap();
+
+    let (tasks, is_idle) = {
+        let mut rq = scheduler.rqs[cpu.as_usize()].lock();
+        debug_assert!(!rq.is_online);
+
+        let mut tasks = Vec::new();
+        while let Some(task) = (rq.stop.pick_next())
+            .or_else(|| rq.real_time.pick_next())
+            .or_else(|| rq.fair.pick_next())
+        {
+            task.schedule_info().cpu.set_to_none();
+            tasks.push(task);
+        }
+
+        scheduler.load_hints.update(cpu, &rq);
+        (tasks, rq.load_stats().is_idle)
+    };
+
+    // The tasks will be picked by the target CPUs at their next ticks, so there is no need to
+    // preempt the current tasks on the target CPUs.
+    for task in tasks {
+        let _ = scheduler.enqueue(task, EnqueueFlags::Wake);
+    }
+
+    is_idle
+}
+
+/// Returns the total time that the CPU has spent running non-idle tasks.
+///
+/// The time is measured in TSC clock units. The scheduling utilization of the CPU can be
+/// calculated by sampling the time periodically.
+pub fn cpu_busy_time(cpu: CpuId) -> u64 {
+    let scheduler = SCHEDULER.get().unwrap();
+    scheduler.rqs[cpu.as_usize()].lock().stats.rq_cpu_time()
+}
+
+/// Returns a snapshot of the scheduler statistics of the CPU.
+pub fn cpu_sched_stats(cpu: CpuId) -> CpuSchedStats {
+    let scheduler = SCHEDULER.get().unwrap();
+    scheduler.rqs[cpu.as_usize()].lock().stats.clone()
+}
+
 /// Represents the middle layer between scheduling classes and generic scheduler
 /// traits. It consists of all the sets of run queues for CPU cores. Other global
 /// information may also be stored here.
@@ -70,6 +184,8 @@ pub struct ClassScheduler {
     /// preventing potential deadlocks due to the fact that
     /// the runqueues may be accessed in both the task and interrupt context (L1 and L2).
     rqs: Box<[SpinLock<PerCpuClassRqSet, LocalIrqDisabled>]>,
+    /// The snapshots of the loads of the run queues.
+    load_hints: LoadHints,
     last_chosen_cpu: AtomicCpuId,
 }
 
@@ -77,11 +193,21 @@ pub struct ClassScheduler {
 /// scheduling classes in its corresponding CPU core. The current task of this CPU
 /// core is also stored in this structure.
 struct PerCpuClassRqSet {
+    cpu: CpuId,
     stop: stop::StopClassRq,
     real_time: real_time::RealTimeClassRq,
     fair: fair::FairClassRq,
     idle: idle::IdleClassRq,
     current: Option<(SchedEntity, CurrentRuntime)>,
+    /// Whether the CPU is online. See [`set_cpu_online`].
+    is_online: bool,
+    /// The scheduler statistics. See [`cpu_sched_stats`].
+    stats: CpuSchedStats,
+    /// Whether some waiting tasks may not be allowed to run on the CPU by their affinity masks.
+    has_disallowed_tasks: bool,
+    /// The address of the task that is most recently switched out, which should not be
+    /// migrated to other CPUs while it is cache-hot.
+    prev_task_addr: usize,
 }
 
 /// Stores the runtime information of the current task.
@@ -136,6 +262,24 @@ trait SchedClassRq: Send + fmt::Debug {
     /// **in this run queue** to replace the current one.
     fn update_current(&mut self, rt: &CurrentRuntime, attr: &SchedAttr, flags: UpdateFlags)
     -> bool;
+
+    /// Removes at most `max` tasks for which `filter` returns `true` and passes them to `detach`.
+    ///
+    /// The tasks that are least likely to run soon are removed first. This is used to migrate
+    /// tasks to other CPUs, so the scheduling classes whose tasks are bound to their CPUs keep the
+    /// default implementation, which removes nothing.
+    fn detach_if(
+        &mut self,
+        _max: usize,
+        _filter: &mut dyn FnMut(&Arc<Task>) -> bool,
+        _detach: &mut dyn FnMut(Arc<Task>),
+    ) {
+    }
+
+    /// Enqueues a task that is removed from another run queue by [`Self::detach_if`].
+    fn attach(&mut self, task: Arc<Task>) {
+        self.enqueue(task, None);
+    }
 }
 
 /// The scheduling attribute for a thread.
@@ -148,6 +292,7 @@ pub struct SchedAttr {
     last_cpu: AtomicCpuId,
     real_time: real_time::RealTimeAttr,
     fair: fair::FairAttr,
+    stats: TaskSchedStats,
 }
 
 impl SchedAttr {
@@ -167,6 +312,7 @@ impl SchedAttr {
                 SchedPolicy::Fair(nice) => nice,
                 _ => Nice::default(),
             }),
+            stats: TaskSchedStats::default(),
         }
     }
 
@@ -212,31 +358,49 @@ impl SchedAttr {
     }
 
     fn set_last_cpu(&self, cpu_id: CpuId) {
+        if self.last_cpu().is_some_and(|last_cpu| last_cpu != cpu_id) {
+            self.stats.inc_migrations();
+        }
         self.last_cpu.set_anyway(cpu_id);
     }
+
+    /// Returns the scheduler statistics of the thread.
+    pub fn stats(&self) -> &TaskSchedStats {
+        &self.stats
+    }
 }
 
 impl Scheduler for ClassScheduler {
     fn enqueue(&self, task: Arc<Task>, flags: EnqueueFlags) -> Option<CpuId> {
         let thread = task.as_thread()?.clone();
 
-        let (still_in_rq, cpu) = {
-            let selected_cpu_id = self.select_cpu(&thread, flags);
+        let (cpu, mut rq) = loop {
+            let (still_in_rq, cpu) = {
+                let selected_cpu_id = self.select_cpu(&thread, flags);
 
-            if let Err(task_cpu_id) = task.cpu().set_if_is_none(selected_cpu_id) {
-                debug_assert!(flags != EnqueueFlags::Spawn);
-                (true, task_cpu_id)
-            } else {
-                (false, selected_cpu_id)
-            }
-        };
+                if let Err(task_cpu_id) = task.cpu().set_if_is_none(selected_cpu_id) {
+                    debug_assert!(flags != EnqueueFlags::Spawn);
+                    (true, task_cpu_id)
+                } else {
+                    (false, selected_cpu_id)
+                }
+            };
 
-        let mut rq = self.rqs[cpu.as_usize()].lock();
+            let rq = self.rqs[cpu.as_usize()].lock();
 
-        // Note: call set_if_is_none again to prevent a race condition.
-        if still_in_rq && task.cpu().set_if_is_none(cpu).is_err() {
-            return None;
-        }
+            // Note: call set_if_is_none again to prevent a race condition.
+            if still_in_rq && task.cpu().set_if_is_none(cpu).is_err() {
+                return None;
+            }
+
+            // The CPU may have been taken offline after it is selected. Since the tasks on an
+            // offline CPU are migrated with the lock held, checking it with the lock held ensures
+            // that no tasks are left on the offline CPU.
+            if rq.is_online {
+                break (cpu, rq);
+            }
+            task.cpu().set_to_none();
+        };
 
         // Preempt if the new task has a higher priority.
         let should_preempt = rq
@@ -246,16 +410,35 @@ impl Scheduler for ClassScheduler {
                 thread.sched_attr().policy() < rq_current_thread.sched_attr().policy()
             });
 
+        if flags == EnqueueFlags::Wake {
+            // The local IRQs are disabled with the lock held, so the current CPU is stable.
+            let this_cpu = CpuId::current_racy();
+            let prev_cpu = thread.sched_attr().last_cpu();
+            let stats = &mut rq.stats;
+            stats.ttwu_count += 1;
+            if cpu == this_cpu {
+                stats.ttwu_local += 1;
+                if prev_cpu.is_some_and(|prev_cpu| prev_cpu != cpu) {
+                    stats.ttwu_move_affine += 1;
+                }
+            } else {
+                stats.ttwu_wake_remote += 1;
+            }
+        }
+
         thread.sched_attr().set_last_cpu(cpu);
         rq.enqueue_entity((task, thread), Some(flags));
+        self.load_hints.update(cpu, &rq);
 
         should_preempt.then_some(cpu)
     }
 
     fn mut_local_rq_with(&self, f: &mut dyn FnMut(&mut dyn LocalRunQueue)) {
         let guard = disable_local();
-        let mut lock = self.rqs[guard.current_cpu().as_usize()].lock();
-        f(&mut *lock)
+        let cpu = guard.current_cpu();
+        let mut lock = self.rqs[cpu.as_usize()].lock();
+        f(&mut *lock);
+        self.load_hints.update(cpu, &lock);
     }
 
     fn local_rq_with(&self, f: &mut dyn FnMut(&dyn LocalRunQueue)) {
@@ -268,52 +451,93 @@ impl ClassScheduler {
     pub fn new() -> Self {
         let class_rq = |cpu| {
             SpinLock::new(PerCpuClassRqSet {
+                cpu,
                 stop: stop::StopClassRq::new(),
                 real_time: real_time::RealTimeClassRq::new(cpu),
                 fair: fair::FairClassRq::new(cpu),
                 idle: idle::IdleClassRq::new(),
                 current: None,
+                is_online: true,
+                stats: CpuSchedStats::default(),
+                has_disallowed_tasks: false,
+                prev_task_addr: 0,
             })
         };
         ClassScheduler {
             rqs: all_cpus().map(class_rq).collect(),
+            load_hints: LoadHints::new(),
             last_chosen_cpu: AtomicCpuId::default(),
         }
     }
 
-    // TODO: Implement a better algorithm and replace the current naive implementation.
+    /// Selects the CPU to run the thread.
+    ///
+    /// A woken thread stays on its previous CPU if the CPU is idle, since its data may still be in
+    /// the cache. Otherwise, it moves to the waker's CPU if that CPU is less loaded, since the two
+    /// threads are likely to share data, or to an idle CPU if there is one. A spawned thread, or a
+    /// woken thread whose previous CPU is no longer allowed, goes to the least loaded CPU.
     fn select_cpu(&self, thread: &Thread, flags: EnqueueFlags) -> CpuId {
-        if let Some(last_cpu) = thread.sched_attr().last_cpu() {
-            return last_cpu;
+        let guard = disable_local();
+        let this_cpu = guard.current_cpu();
+
+        let mut allowed_cpus =
+            hotplug::online_cpus_in(&thread.atomic_cpu_affinity().load(Ordering::Relaxed));
+        if allowed_cpus.is_empty() {
+            // All the allowed CPUs have been taken offline. Like Linux, fall back to any CPU.
+            allowed_cpus = hotplug::online_cpus();
         }
-        debug_assert!(flags == EnqueueFlags::Spawn);
 
-        let guard = disable_local();
+        match thread.sched_attr().last_cpu() {
+            Some(prev_cpu) if allowed_cpus.contains(prev_cpu) => {
+                debug_assert!(flags == EnqueueFlags::Wake);
+                self.select_cpu_on_wake(prev_cpu, this_cpu, &allowed_cpus)
+            }
+            _ => self.select_least_loaded_cpu(this_cpu, &allowed_cpus),
+        }
+    }
+
+    fn select_cpu_on_wake(&self, prev_cpu: CpuId, this_cpu: CpuId, allowed_cpus: &CpuSet) -> CpuId {
+        let hints = &self.load_hints;
+        if hints.is_idle(prev_cpu) {
+            return prev_cpu;
+        }
 
-        let mut selected = guard.current_cpu();
+        let target_cpu = if this_cpu != prev_cpu
+            && allowed_cpus.contains(this_cpu)
+            && hints.load(this_cpu) < hints.load(prev_cpu)
+        {
+            this_cpu
+        } else {
+            prev_cpu
+        };
+
+        Self::cycle_after(prev_cpu, allowed_cpus)
+            .find(|cpu| hints.is_idle(*cpu))
+            .unwrap_or(target_cpu)
+    }
+
+    fn select_least_loaded_cpu(&self, this_cpu: CpuId, allowed_cpus: &CpuSet) -> CpuId {
+        let mut selected = this_cpu;
         let mut minimum_load = u32::MAX;
 
         // Set `selected` as `candidate` if the candidate's load is smaller.
         let test_candidate = |candidate: CpuId| {
-            let PerCpuLoadStats { queue_len, .. } =
-                self.rqs[candidate.as_usize()].lock().load_stats();
-            let load = queue_len;
+            let load = self.load_hints.load(candidate);
             if load < minimum_load {
                 minimum_load = load;
                 selected = candidate;
             }
         };
 
-        let affinity = thread.atomic_cpu_affinity().load(Ordering::Relaxed);
         match self.last_chosen_cpu.get() {
             Some(cpu) => {
                 // Perform a round-robin selection starting after the last chosen CPU.
                 //
                 // It still checks every CPU in the affinity set to find the one with the
                 // minimum load, but avoids selecting the same CPU again in case of a tie.
-                Self::cycle_after(cpu, &affinity).for_each(test_candidate)
+                Self::cycle_after(cpu, allowed_cpus).for_each(test_candidate)
             }
-            None => affinity.iter().for_each(test_candidate),
+            None => allowed_cpus.iter().for_each(test_candidate),
         }
 
         self.last_chosen_cpu.set_anyway(selected);
@@ -336,18 +560,42 @@ impl ClassScheduler {
 
 impl PerCpuClassRqSet {
     fn pick_next_entity(&mut self) -> Option<SchedEntity> {
-        (self.stop.pick_next())
-            .or_else(|| self.real_time.pick_next())
-            .or_else(|| self.fair.pick_next())
-            .or_else(|| self.idle.pick_next())
-            .and_then(|task| {
-                let thread = task.as_thread()?.clone();
-                Some((task, thread))
-            })
+        // A current task that is no longer allowed to run on this CPU is switched to the idle
+        // task rather than the other waiting tasks, so that the idle task pushes it to an allowed
+        // CPU at once (see `balance::idle_balance`).
+        let is_current_disallowed = self.is_current_disallowed();
+        let next = (self.stop.pick_next()).or_else(|| {
+            if is_current_disallowed {
+                self.idle.pick_next()
+            } else {
+                (self.real_time.pick_next())
+                    .or_else(|| self.fair.pick_next())
+                    .or_else(|| self.idle.pick_next())
+            }
+        });
+        next.and_then(|task| {
+            let thread = task.as_thread()?.clone();
+            Some((task, thread))
+        })
+        .inspect(|(_, thread)| {
+            let attr = thread.sched_attr();
+            self.stats.sched_count += 1;
+            if attr.policy_kind() == SchedPolicyKind::Idle {
+                self.stats.sched_goidle += 1;
+            } else {
+                let delay = attr.stats.end_wait(sched_clock());
+                self.stats.account_pick(delay);
+            }
+        })
     }
 
     fn enqueue_entity(&mut self, (task, thread): SchedEntity, flags: Option<EnqueueFlags>) {
-        match thread.sched_attr().policy_kind() {
+        let attr = thread.sched_attr();
+        if attr.policy_kind() != SchedPolicyKind::Idle {
+            attr.stats.start_wait(sched_clock());
+        }
+
+        match attr.policy_kind() {
             SchedPolicyKind::Stop => self.stop.enqueue(task, flags),
             SchedPolicyKind::RealTime => self.real_time.enqueue(task, flags),
             SchedPolicyKind::Fair => self.fair.enqueue(task, flags),
@@ -355,6 +603,19 @@ impl PerCpuClassRqSet {
         }
     }
 
+    /// Returns whether the current task is excluded from this CPU by its affinity mask.
+    ///
+    /// Only the tasks that can be migrated, i.e., the real-time and fair tasks, are checked.
+    fn is_current_disallowed(&self) -> bool {
+        let Some(((task, thread), _)) = &self.current else {
+            return false;
+        };
+        matches!(
+            thread.sched_attr().policy_kind(),
+            SchedPolicyKind::RealTime | SchedPolicyKind::Fair
+        ) && !balance::is_allowed_on(task, self.cpu)
+    }
+
     fn load_stats(&self) -> PerCpuLoadStats {
         let queue_len = (self.stop.len() + self.real_time.len() + self.fair.len()) as u32;
         let is_idle = match &self.current {
@@ -372,9 +633,19 @@ impl LocalRunQueue for PerCpuClassRqSet {
 
     fn try_pick_next(&mut self) -> Option<&Arc<Task>> {
         self.pick_next_entity().and_then(|next| {
+            // Looking up the thread IDs is not free, so it is skipped if the tracepoint is off.
+            if SCHED_SWITCH.is_enabled() {
+                // The current task is still in `self.current` unless it has been dequeued
+                // because it is waiting or exiting.
+                let prev_state = if self.current.is_some() { 'R' } else { 'S' };
+                trace_sched_switch(current_tid(), prev_state, task_tid(&next.0));
+            }
+
             // We guarantee that a task can appear at once in a `PerCpuClassRqSet`. So, the `next` cannot be the same
             // as the current task here.
             if let Some((old, _)) = self.current.replace((next, CurrentRuntime::new())) {
+                self.prev_task_addr = Arc::as_ptr(&old.0) as usize;
+                old.1.sched_attr().stats.inc_switches(false);
                 self.enqueue_entity(old, None);
             }
             self.current.as_ref().map(|((task, _), _)| task)
@@ -385,6 +656,13 @@ impl LocalRunQueue for PerCpuClassRqSet {
         let (should_preempt, mut lookahead) = if let Some(((_, cur), rt)) = &mut self.current {
             rt.update();
             let attr = &cur.sched_attr();
+            if attr.policy_kind() != SchedPolicyKind::Idle {
+                attr.stats.account_runtime(rt.delta);
+                self.stats.account_runtime(rt.delta);
+            }
+            if flags == UpdateFlags::Yield {
+                self.stats.yld_count += 1;
+            }
 
             match attr.policy_kind() {
                 SchedPolicyKind::Stop => (self.stop.update_current(rt, attr, flags), 0),
@@ -396,7 +674,12 @@ impl LocalRunQueue for PerCpuClassRqSet {
             (false, 4)
         };
 
-        if matches!(flags, UpdateFlags::Wait | UpdateFlags::Exit) {
+        // An offline CPU should switch to its idle task as soon as possible. So should a CPU whose
+        // current task is no longer allowed to run on it, after which the task is migrated.
+        if matches!(flags, UpdateFlags::Wait | UpdateFlags::Exit) || !self.is_online {
+            lookahead = 4;
+        } else if self.is_current_disallowed() {
+            self.has_disallowed_tasks = true;
             lookahead = 4;
         }
 
@@ -408,7 +691,8 @@ impl LocalRunQueue for PerCpuClassRqSet {
     }
 
     fn dequeue_current(&mut self) -> Option<Arc<Task>> {
-        self.current.take().map(|((cur_task, _), _)| {
+        self.current.take().map(|((cur_task, cur_thread), _)| {
+            cur_thread.sched_attr().stats.inc_switches(true);
             cur_task.schedule_info().cpu.set_to_none();
             cur_task
         })
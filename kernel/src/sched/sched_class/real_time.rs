@@ -136,6 +136,35 @@ impl PrioArray {
         let prio = self.map.iter_ones().next()?;
         Some(prio as u8)
     }
+
+    /// Removes at most `max` threads, starting from the lowest priority, and returns the number
+    /// of removed threads.
+    fn detach_if(
+        &mut self,
+        max: usize,
+        filter: &mut dyn FnMut(&Arc<Task>) -> bool,
+        detach: &mut dyn FnMut(Arc<Task>),
+    ) -> usize {
+        let mut nr_detached = 0;
+
+        for (prio, queue) in self.queue.iter_mut().enumerate().rev() {
+            let mut index = 0;
+            while index < queue.len() && nr_detached < max {
+                if filter(&queue[index]) {
+                    detach(queue.remove(index).unwrap());
+                    nr_detached += 1;
+                } else {
+                    index += 1;
+                }
+            }
+
+            if queue.is_empty() {
+                self.map.set(prio, false);
+            }
+        }
+
+        nr_detached
+    }
 }
 
 /// The per-cpu run queue for the REAL-TIME scheduling class.
@@ -235,4 +264,18 @@ impl SchedClassRq for RealTimeClassRq {
             UpdateFlags::Wait | UpdateFlags::Exit => !self.is_empty(),
         }
     }
+
+    fn detach_if(
+        &mut self,
+        max: usize,
+        filter: &mut dyn FnMut(&Arc<Task>) -> bool,
+        detach: &mut dyn FnMut(Arc<Task>),
+    ) {
+        // The threads in the inactive array run after those in the active array.
+        let inactive_detached = self.inactive_array().detach_if(max, filter, detach);
+        let active_detached =
+            self.active_array()
+                .detach_if(max - inactive_detached, filter, detach);
+        self.nr_running -= inactive_detached + active_detached;
+    }
 }
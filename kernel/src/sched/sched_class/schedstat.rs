@@ -0,0 +1,202 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Scheduler statistics.
+//!
+//! The scheduler accounts how long the tasks wait in the run queues before they run, how long
+//! they run, and how often they are switched and migrated, both for every task and for every CPU.
+//! The statistics are exported via `/proc/schedstat`, `/proc/[pid]/schedstat`, and
+//! `/proc/[pid]/sched` in the same formats as Linux, so that the scheduling latencies can be
+//! investigated with the existing tools.
+//!
+//! The times are accumulated in TSC clock units and converted to nanoseconds when they are read.
+//!
+//! Reference: <https://docs.kernel.org/scheduler/sched-stats.html>
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use super::time::clocks_to_ns;
+
+/// The scheduler statistics of a task.
+#[derive(Debug, Default)]
+pub struct TaskSchedStats {
+    /// The total time spent running on CPUs.
+    exec_runtime: AtomicU64,
+    /// The total time spent waiting in run queues.
+    run_delay: AtomicU64,
+    /// The longest time spent waiting in a run queue at a time.
+    wait_max: AtomicU64,
+    /// The time when the task began to wait in a run queue, or zero if it is not waiting.
+    wait_start: AtomicU64,
+    /// The number of times that the task has been picked to run.
+    pcount: AtomicU64,
+    nr_migrations: AtomicU64,
+    nr_voluntary_switches: AtomicU64,
+    nr_involuntary_switches: AtomicU64,
+}
+
+impl TaskSchedStats {
+    /// Returns the total time spent running on CPUs in nanoseconds.
+    pub fn exec_runtime_ns(&self) -> u64 {
+        clocks_to_ns(self.exec_runtime.load(Ordering::Relaxed))
+    }
+
+    /// Returns the total time spent waiting in run queues in nanoseconds.
+    pub fn run_delay_ns(&self) -> u64 {
+        clocks_to_ns(self.run_delay.load(Ordering::Relaxed))
+    }
+
+    /// Returns the longest time spent waiting in a run queue at a time in nanoseconds.
+    pub fn wait_max_ns(&self) -> u64 {
+        clocks_to_ns(self.wait_max.load(Ordering::Relaxed))
+    }
+
+    /// Returns the number of times that the task has been picked to run.
+    pub fn pcount(&self) -> u64 {
+        self.pcount.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of times that the task has been moved to a different CPU.
+    pub fn nr_migrations(&self) -> u64 {
+        self.nr_migrations.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of times that the task has been switched out because it waits or exits.
+    pub fn nr_voluntary_switches(&self) -> u64 {
+        self.nr_voluntary_switches.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of times that the task has been switched out while it is runnable.
+    pub fn nr_involuntary_switches(&self) -> u64 {
+        self.nr_involuntary_switches.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn start_wait(&self, now: u64) {
+        self.wait_start.store(now, Ordering::Relaxed);
+    }
+
+    /// Ends the wait in a run queue and returns its length.
+    pub(super) fn end_wait(&self, now: u64) -> u64 {
+        let wait_start = self.wait_start.swap(0, Ordering::Relaxed);
+        // The task may have begun to wait on another CPU, whose TSC may be slightly ahead.
+        let delay = if wait_start == 0 {
+            0
+        } else {
+            now.saturating_sub(wait_start)
+        };
+
+        self.run_delay.fetch_add(delay, Ordering::Relaxed);
+        self.wait_max.fetch_max(delay, Ordering::Relaxed);
+        self.pcount.fetch_add(1, Ordering::Relaxed);
+        delay
+    }
+
+    pub(super) fn account_runtime(&self, delta: u64) {
+        self.exec_runtime.fetch_add(delta, Ordering::Relaxed);
+    }
+
+    pub(super) fn inc_migrations(&self) {
+        self.nr_migrations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn inc_switches(&self, is_voluntary: bool) {
+        if is_voluntary {
+            self.nr_voluntary_switches.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.nr_involuntary_switches.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// The state of a CPU when it balances the load.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CpuIdleType {
+    /// The CPU is idle and balances the load periodically.
+    Idle = 0,
+    /// The CPU is busy and balances the load periodically.
+    NotIdle = 1,
+    /// The CPU is about to become idle.
+    NewlyIdle = 2,
+}
+
+impl CpuIdleType {
+    /// All the types in the order of `/proc/schedstat`.
+    pub const ALL: [Self; 3] = [Self::Idle, Self::NotIdle, Self::NewlyIdle];
+}
+
+/// The statistics of the load balancing attempts of a CPU.
+#[derive(Debug, Clone, Default)]
+pub struct LoadBalanceStats {
+    /// The number of attempts to pull tasks.
+    pub count: u64,
+    /// The number of attempts that find the load already balanced.
+    pub balanced: u64,
+    /// The number of attempts that find an imbalance but pull no tasks.
+    pub failed: u64,
+    /// The total number of tasks that are found to be imbalanced.
+    pub imbalance: u64,
+    /// The number of pulled tasks.
+    pub gained: u64,
+    /// The number of attempts that find no busier CPUs.
+    pub no_busy_queue: u64,
+}
+
+/// The scheduler statistics of a CPU.
+#[derive(Debug, Clone, Default)]
+pub struct CpuSchedStats {
+    /// The number of times that the current task yields.
+    pub yld_count: u64,
+    /// The number of times that the next task is picked.
+    pub sched_count: u64,
+    /// The number of times that the idle task is picked.
+    pub sched_goidle: u64,
+    /// The number of tasks woken up to run on the CPU.
+    pub ttwu_count: u64,
+    /// The number of tasks woken up by a task on the same CPU.
+    pub ttwu_local: u64,
+    /// The number of tasks woken up by a task on another CPU.
+    pub ttwu_wake_remote: u64,
+    /// The number of tasks moved to the CPU of their wakers when they are woken up.
+    pub ttwu_move_affine: u64,
+    /// The number of times that non-idle tasks are picked to run.
+    pub pcount: u64,
+    /// The total time spent running non-idle tasks.
+    rq_cpu_time: u64,
+    /// The total time that the picked tasks have spent waiting in the run queue.
+    run_delay: u64,
+    lb: [LoadBalanceStats; 3],
+}
+
+impl CpuSchedStats {
+    /// Returns the total time spent running non-idle tasks in nanoseconds.
+    pub fn rq_cpu_time_ns(&self) -> u64 {
+        clocks_to_ns(self.rq_cpu_time)
+    }
+
+    /// Returns the total time that the picked tasks have spent waiting in nanoseconds.
+    pub fn run_delay_ns(&self) -> u64 {
+        clocks_to_ns(self.run_delay)
+    }
+
+    /// Returns the statistics of load balancing when the CPU is in the given state.
+    pub fn lb(&self, idle_type: CpuIdleType) -> &LoadBalanceStats {
+        &self.lb[idle_type as usize]
+    }
+
+    pub(super) fn lb_mut(&mut self, idle_type: CpuIdleType) -> &mut LoadBalanceStats {
+        &mut self.lb[idle_type as usize]
+    }
+
+    /// Returns the total time spent running non-idle tasks in TSC clock units.
+    pub(super) fn rq_cpu_time(&self) -> u64 {
+        self.rq_cpu_time
+    }
+
+    pub(super) fn account_runtime(&mut self, delta: u64) {
+        self.rq_cpu_time += delta;
+    }
+
+    pub(super) fn account_pick(&mut self, delay: u64) {
+        self.run_delay += delay;
+        self.pcount += 1;
+    }
+}
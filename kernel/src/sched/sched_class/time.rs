@@ -51,3 +51,9 @@ pub fn base_slice_clocks() -> u64 {
 pub fn min_period_clocks() -> u64 {
     consts().1
 }
+
+/// Converts a duration in TSC clock units to nanoseconds.
+pub fn clocks_to_ns(clocks: u64) -> u64 {
+    let (a, b) = tsc_factors();
+    (clocks as u128 * a as u128 / b as u128) as u64
+}
@@ -0,0 +1,63 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::policy::Profile;
+use crate::prelude::*;
+
+/// The AppArmor attributes of a thread.
+pub(crate) struct AppArmorContext {
+    /// The profile that confines the thread, or `None` if the thread is unconfined.
+    profile: Option<Arc<Profile>>,
+    /// The profile to switch to on the next `execve`, which is set via `/proc/[pid]/attr/exec`.
+    exec_profile: Option<Arc<Profile>>,
+    /// The profile prepared for the ongoing `execve`.
+    ///
+    /// The outer `Option` is `None` if no profile is prepared, and the inner `Option` is `None`
+    /// if the new program is unconfined.
+    pending_profile: Option<Option<Arc<Profile>>>,
+}
+
+impl AppArmorContext {
+    /// Creates the attributes of a thread that is confined by the profile.
+    pub(crate) fn new(profile: Option<Arc<Profile>>) -> Self {
+        Self {
+            profile,
+            exec_profile: None,
+            pending_profile: None,
+        }
+    }
+
+    /// Returns the profile that confines the thread.
+    ///
+    /// A profile that has been removed no longer confines the thread.
+    pub(crate) fn profile(&self) -> Option<Arc<Profile>> {
+        self.profile
+            .as_ref()
+            .filter(|profile| !profile.is_removed())
+            .cloned()
+    }
+
+    /// Returns the profile to switch to on the next `execve`.
+    pub(super) fn exec_profile(&self) -> Option<Arc<Profile>> {
+        self.exec_profile
+            .as_ref()
+            .filter(|profile| !profile.is_removed())
+            .cloned()
+    }
+
+    pub(super) fn set_exec_profile(&mut self, profile: Arc<Profile>) {
+        self.exec_profile = Some(profile);
+    }
+
+    /// Prepares the profile for the ongoing `execve`.
+    pub(super) fn prepare_exec(&mut self, profile: Option<Arc<Profile>>) {
+        self.pending_profile = Some(profile);
+    }
+
+    /// Switches to the profile prepared for the ongoing `execve`.
+    pub(super) fn commit_exec(&mut self) {
+        if let Some(profile) = self.pending_profile.take() {
+            self.profile = profile;
+        }
+        self.exec_profile = None;
+    }
+}
@@ -0,0 +1,223 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::prelude::*;
+
+/// The maximum number of the alternatives of a glob after the braces are expanded.
+const MAX_ALTERNATIVES: usize = 64;
+
+/// A glob that matches the full names of files.
+///
+/// The special characters are:
+/// - `*`, which matches any characters except `/`;
+/// - `**`, which matches any characters, including `/`;
+/// - `?`, which matches any character except `/`;
+/// - `{a,b}`, which matches either `a` or `b`, where `a` and `b` can also be globs;
+/// - `\`, which escapes the next character.
+///
+/// Unlike AppArmor, which compiles the globs into a DFA, the globs are matched by backtracking.
+///
+/// Reference: <https://manpages.ubuntu.com/manpages/noble/man5/apparmor.d.5.html>.
+#[derive(Debug)]
+pub(super) struct Glob {
+    pattern: String,
+    /// The globs that have no braces, one of which must match.
+    alternatives: Vec<Vec<Token>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    Literal(u8),
+    /// `?`.
+    AnyChar,
+    /// `*`.
+    AnyInComponent,
+    /// `**`.
+    Any,
+}
+
+impl Glob {
+    /// Parses the glob.
+    pub(super) fn new(pattern: &str) -> Result<Self> {
+        let alternatives = expand_braces(pattern.as_bytes())?
+            .iter()
+            .map(|alternative| tokenize(alternative))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            pattern: pattern.to_string(),
+            alternatives,
+        })
+    }
+
+    /// Returns the original pattern.
+    pub(super) fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Returns whether the glob matches the name.
+    pub(super) fn matches(&self, name: &str) -> bool {
+        self.alternatives
+            .iter()
+            .any(|tokens| matches_tokens(tokens, name.as_bytes()))
+    }
+}
+
+/// Expands the braces, returning the globs that have no braces.
+fn expand_braces(pattern: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let Some(start) = find_unescaped(pattern, b'{') else {
+        return Ok(vec![pattern.to_vec()]);
+    };
+
+    let mut options = Vec::new();
+    let mut option_start = start + 1;
+    let mut end = None;
+    let mut depth = 0;
+    let mut i = start + 1;
+    while i < pattern.len() {
+        match pattern[i] {
+            b'\\' => i += 1,
+            b'{' => depth += 1,
+            b'}' if depth == 0 => {
+                options.push(&pattern[option_start..i]);
+                end = Some(i);
+                break;
+            }
+            b'}' => depth -= 1,
+            b',' if depth == 0 => {
+                options.push(&pattern[option_start..i]);
+                option_start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    let Some(end) = end else {
+        return_errno_with_message!(Errno::EINVAL, "the braces in the glob are unbalanced");
+    };
+
+    let (prefix, suffix) = (&pattern[..start], &pattern[end + 1..]);
+    let mut expanded = Vec::new();
+    for option in options {
+        let mut alternative = prefix.to_vec();
+        alternative.extend_from_slice(option);
+        alternative.extend_from_slice(suffix);
+        expanded.extend(expand_braces(&alternative)?);
+
+        if expanded.len() > MAX_ALTERNATIVES {
+            return_errno_with_message!(Errno::EINVAL, "the glob has too many alternatives");
+        }
+    }
+    Ok(expanded)
+}
+
+/// Finds the first occurrence of the byte that is not escaped.
+fn find_unescaped(pattern: &[u8], byte: u8) -> Option<usize> {
+    let mut i = 0;
+    while i < pattern.len() {
+        if pattern[i] == b'\\' {
+            i += 2;
+            continue;
+        }
+        if pattern[i] == byte {
+            return Some(i);
+        }
+        i += 1;
+    }
+    None
+}
+
+fn tokenize(pattern: &[u8]) -> Result<Vec<Token>> {
+    let mut tokens = Vec::with_capacity(pattern.len());
+
+    let mut bytes = pattern.iter().copied().peekable();
+    while let Some(byte) = bytes.next() {
+        let token = match byte {
+            b'\\' => {
+                let Some(escaped) = bytes.next() else {
+                    return_errno_with_message!(Errno::EINVAL, "the glob ends with a backslash");
+                };
+                Token::Literal(escaped)
+            }
+            b'?' => Token::AnyChar,
+            b'*' if bytes.peek() == Some(&b'*') => {
+                while bytes.next_if_eq(&b'*').is_some() {}
+                Token::Any
+            }
+            b'*' => Token::AnyInComponent,
+            b'}' => {
+                return_errno_with_message!(Errno::EINVAL, "the braces in the glob are unbalanced")
+            }
+            b'[' | b']' => {
+                return_errno_with_message!(
+                    Errno::EINVAL,
+                    "character classes in the glob are not supported"
+                )
+            }
+            _ => Token::Literal(byte),
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+fn matches_tokens(tokens: &[Token], name: &[u8]) -> bool {
+    let Some((first, rest)) = tokens.split_first() else {
+        return name.is_empty();
+    };
+
+    match first {
+        Token::Literal(byte) => name.first() == Some(byte) && matches_tokens(rest, &name[1..]),
+        Token::AnyChar => {
+            name.first().is_some_and(|byte| *byte != b'/') && matches_tokens(rest, &name[1..])
+        }
+        Token::AnyInComponent => {
+            let max_len = name
+                .iter()
+                .position(|byte| *byte == b'/')
+                .unwrap_or(name.len());
+            (0..=max_len).any(|len| matches_tokens(rest, &name[len..]))
+        }
+        Token::Any => (0..=name.len()).any(|len| matches_tokens(rest, &name[len..])),
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::Glob;
+
+    #[ktest]
+    fn match_wildcards() {
+        let glob = Glob::new("/etc/*.conf").unwrap();
+        assert!(glob.matches("/etc/host.conf"));
+        assert!(glob.matches("/etc/.conf"));
+        assert!(!glob.matches("/etc/ssh/sshd.conf"));
+
+        let glob = Glob::new("/usr/**").unwrap();
+        assert!(glob.matches("/usr/lib/libc.so"));
+        assert!(!glob.matches("/usr"));
+
+        let glob = Glob::new("/tmp/file?").unwrap();
+        assert!(glob.matches("/tmp/file1"));
+        assert!(!glob.matches("/tmp/file/"));
+        assert!(!glob.matches("/tmp/file10"));
+    }
+
+    #[ktest]
+    fn match_alternatives() {
+        let glob = Glob::new("/{bin,usr/{bin,sbin}}/sh").unwrap();
+        assert!(glob.matches("/bin/sh"));
+        assert!(glob.matches("/usr/bin/sh"));
+        assert!(glob.matches("/usr/sbin/sh"));
+        assert!(!glob.matches("/usr/sh"));
+
+        let glob = Glob::new("/a\\{b\\}").unwrap();
+        assert!(glob.matches("/a{b}"));
+
+        assert!(Glob::new("/{a,b").is_err());
+        assert!(Glob::new("/a}").is_err());
+        assert!(Glob::new("/[ab]").is_err());
+    }
+}
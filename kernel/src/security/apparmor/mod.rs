@@ -0,0 +1,391 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! AppArmor, a security module that confines programs with profiles.
+//!
+//! A profile specifies the files, the capabilities, and the sockets that the confined threads
+//! can access. The profiles are written in a policy language (see [`parser`]) and are loaded by
+//! writing to `.load` or `.replace` in the `apparmor` directory of securityfs. A thread becomes
+//! confined when it executes a program to which a profile is attached, or when it executes any
+//! program after writing `exec NAME` to `/proc/self/attr/exec`.
+//!
+//! In the enforce mode, the accesses that a profile does not allow are denied. In the complain
+//! mode, such accesses are allowed. In both modes, the accesses are logged by the audit
+//! subsystem.
+//!
+//! Unlike Linux, the policy is loaded as text rather than as the binary policy compiled by
+//! `apparmor_parser`, and the profiles cannot have hats or child profiles. In addition, only the
+//! capabilities checked by [`UserNamespace::check_cap`] are confined.
+//!
+//! Reference: <https://docs.kernel.org/admin-guide/LSM/apparmor.html>.
+
+mod context;
+mod glob;
+mod parser;
+mod policy;
+
+use alloc::format;
+
+pub(crate) use self::{context::AppArmorContext, policy::Profile};
+use self::{
+    parser::{CAPABILITY_NAMES, FAMILY_NAMES, TYPE_NAMES},
+    policy::{ExecMode, FilePerms, Mode},
+};
+use super::{
+    audit::{self, UntrustedStr},
+    lsm::SecurityModule,
+};
+use crate::{
+    fs::{
+        file::{AccessMode, InodeType},
+        securityfs::{self, SecurityDir, SecurityFile},
+        vfs::path::Path,
+    },
+    prelude::*,
+    process::{
+        UserNamespace,
+        credentials::capabilities::CapSet,
+        posix_thread::{AsPosixThread, PosixThread},
+    },
+    thread::Thread,
+    util::net::{CSocketAddrFamily, SockType},
+};
+
+/// The name of the security module.
+const LSM_NAME: &str = "apparmor";
+
+/// The AppArmor security module.
+pub(super) struct AppArmor;
+
+impl SecurityModule for AppArmor {
+    fn name(&self) -> &'static str {
+        LSM_NAME
+    }
+
+    fn file_open(&self, path: &Path, access_mode: AccessMode) -> Result<()> {
+        let Some(profile) = current_profile() else {
+            return Ok(());
+        };
+        // Like Linux, the files without names (e.g., pipes) are not confined.
+        if path.is_pseudo() {
+            return Ok(());
+        }
+
+        let mut perms = FilePerms::empty();
+        if access_mode.is_readable() {
+            perms |= FilePerms::READ;
+        }
+        if access_mode.is_writable() {
+            perms |= FilePerms::WRITE;
+        }
+        check_file(&profile, "open", &file_name(path), perms)
+    }
+
+    fn path_mknod(&self, dir: &Path, name: &str, type_: InodeType) -> Result<()> {
+        let Some(profile) = current_profile() else {
+            return Ok(());
+        };
+        let is_dir = type_ == InodeType::Dir;
+        let operation = if is_dir { "mkdir" } else { "mknod" };
+        let name = child_name(dir, name, is_dir);
+        check_file(&profile, operation, &name, FilePerms::WRITE)
+    }
+
+    fn path_unlink(&self, dir: &Path, name: &str) -> Result<()> {
+        let Some(profile) = current_profile() else {
+            return Ok(());
+        };
+        let name = child_name(dir, name, false);
+        check_file(&profile, "unlink", &name, FilePerms::WRITE)
+    }
+
+    fn path_rmdir(&self, dir: &Path, name: &str) -> Result<()> {
+        let Some(profile) = current_profile() else {
+            return Ok(());
+        };
+        let name = child_name(dir, name, true);
+        check_file(&profile, "rmdir", &name, FilePerms::WRITE)
+    }
+
+    fn path_link(&self, _old: &Path, new_dir: &Path, new_name: &str) -> Result<()> {
+        let Some(profile) = current_profile() else {
+            return Ok(());
+        };
+        let name = child_name(new_dir, new_name, false);
+        check_file(&profile, "link", &name, FilePerms::LINK)
+    }
+
+    fn path_rename(
+        &self,
+        _old_dir: &Path,
+        old: &Path,
+        new_dir: &Path,
+        new_name: &str,
+        _new: Option<&Path>,
+    ) -> Result<()> {
+        let Some(profile) = current_profile() else {
+            return Ok(());
+        };
+        let old_perms = FilePerms::READ | FilePerms::WRITE;
+        check_file(&profile, "rename_src", &file_name(old), old_perms)?;
+        let name = child_name(new_dir, new_name, old.type_() == InodeType::Dir);
+        check_file(&profile, "rename_dest", &name, FilePerms::WRITE)
+    }
+
+    fn path_truncate(&self, path: &Path) -> Result<()> {
+        let Some(profile) = current_profile() else {
+            return Ok(());
+        };
+        check_file(&profile, "truncate", &file_name(path), FilePerms::WRITE)
+    }
+
+    fn bprm_creds_for_exec(&self, path: &Path) -> Result<()> {
+        let Some(thread) = Thread::current() else {
+            return Ok(());
+        };
+        let Some(posix_thread) = thread.as_posix_thread() else {
+            return Ok(());
+        };
+
+        let (profile, exec_profile) = {
+            let context = posix_thread.apparmor_context().lock();
+            (context.profile(), context.exec_profile())
+        };
+        let name = path.full_name();
+        let new_profile = match profile {
+            Some(profile) => exec_transition(&profile, &name)?,
+            None => policy::find_attached_profile(&name),
+        };
+
+        posix_thread
+            .apparmor_context()
+            .lock()
+            .prepare_exec(exec_profile.or(new_profile));
+        Ok(())
+    }
+
+    fn bprm_committing_creds(&self) {
+        let Some(thread) = Thread::current() else {
+            return;
+        };
+        let Some(posix_thread) = thread.as_posix_thread() else {
+            return;
+        };
+        posix_thread.apparmor_context().lock().commit_exec();
+    }
+
+    fn capable(&self, posix_thread: &PosixThread, cap: CapSet) -> Result<()> {
+        let Some(profile) = posix_thread.apparmor_context().lock().profile() else {
+            return Ok(());
+        };
+
+        let data = profile.data();
+        let is_allowed = data.allow.caps.contains(cap);
+        let is_denied = data.deny.caps.intersects(cap);
+        check_access(&profile, "capable", is_allowed, is_denied, Errno::EPERM, || {
+            let bit = cap.bits().trailing_zeros() as usize;
+            let name = CAPABILITY_NAMES.get(bit).copied().unwrap_or("unknown");
+            format!("capability={} capname=\"{}\"", bit, name)
+        })
+    }
+
+    fn socket_create(
+        &self,
+        family: CSocketAddrFamily,
+        type_: SockType,
+        protocol: i32,
+    ) -> Result<()> {
+        let Some(profile) = current_profile() else {
+            return Ok(());
+        };
+
+        let data = profile.data();
+        let is_allowed = data.allow.has_network(family, type_);
+        let is_denied = data.deny.has_network(family, type_);
+        check_access(&profile, "create", is_allowed, is_denied, Errno::EACCES, || {
+            let family_name = FAMILY_NAMES
+                .iter()
+                .find(|(_, name_family)| *name_family == family)
+                .map_or("unknown", |(name, _)| *name);
+            let type_name = TYPE_NAMES
+                .iter()
+                .find(|(_, name_type)| *name_type == type_)
+                .map_or("unknown", |(name, _)| *name);
+            format!(
+                "family=\"{}\" sock_type=\"{}\" protocol={}",
+                family_name, type_name, protocol
+            )
+        })
+    }
+}
+
+/// Returns the profile that confines the current thread.
+fn current_profile() -> Option<Arc<Profile>> {
+    let thread = Thread::current()?;
+    thread.as_posix_thread()?.apparmor_context().lock().profile()
+}
+
+/// Returns the full name of the file, which ends with `/` if the file is a directory.
+fn file_name(path: &Path) -> String {
+    let mut name = path.full_name();
+    if path.type_() == InodeType::Dir && !name.ends_with('/') {
+        name.push('/');
+    }
+    name
+}
+
+/// Returns the full name of the file with the name in the directory.
+fn child_name(dir: &Path, name: &str, is_dir: bool) -> String {
+    let mut full_name = dir.full_name();
+    if !full_name.ends_with('/') {
+        full_name.push('/');
+    }
+    full_name.push_str(name);
+    if is_dir {
+        full_name.push('/');
+    }
+    full_name
+}
+
+/// Checks whether the profile allows the permissions to the file with the full name.
+fn check_file(profile: &Profile, operation: &str, name: &str, perms: FilePerms) -> Result<()> {
+    let data = profile.data();
+    let is_allowed = data.allow.file_perms(name).contains(perms);
+    let is_denied = data.deny.file_perms(name).intersects(perms);
+    check_access(profile, operation, is_allowed, is_denied, Errno::EACCES, || {
+        format!(
+            "name={} requested_mask=\"{}\"",
+            UntrustedStr(name.as_bytes()),
+            perms
+        )
+    })
+}
+
+/// Checks whether the profile allows executing the file with the full name, and returns the
+/// profile that confines the new program.
+fn exec_transition(profile: &Arc<Profile>, name: &str) -> Result<Option<Arc<Profile>>> {
+    check_file(profile, "exec", name, FilePerms::EXEC)?;
+
+    match profile.data().allow.exec_mode(name) {
+        // If there is no exec mode, the profile is in the complain mode.
+        None | Some(ExecMode::Inherit) => Ok(Some(profile.clone())),
+        Some(ExecMode::Unconfined) => Ok(None),
+        Some(ExecMode::Profile) => {
+            if let Some(new_profile) = policy::find_attached_profile(name) {
+                return Ok(Some(new_profile));
+            }
+            check_access(profile, "exec", false, false, Errno::EACCES, || {
+                format!(
+                    "name={} info=\"no profile is attached\"",
+                    UntrustedStr(name.as_bytes())
+                )
+            })?;
+            Ok(Some(profile.clone()))
+        }
+    }
+}
+
+/// Checks the access based on whether the profile allows or denies it.
+///
+/// If the access is not allowed, it is logged. Then, the access fails with `errno` if the profile
+/// is in the enforce mode or if the profile explicitly denies the access. `object` describes the
+/// object of the access in the audit record.
+fn check_access(
+    profile: &Profile,
+    operation: &str,
+    is_allowed: bool,
+    is_denied: bool,
+    errno: Errno,
+    object: impl FnOnce() -> String,
+) -> Result<()> {
+    if is_allowed && !is_denied {
+        return Ok(());
+    }
+
+    let object = format!(
+        "profile={} {}",
+        UntrustedStr(profile.name().as_bytes()),
+        object()
+    );
+    if !is_denied && profile.data().mode == Mode::Complain {
+        audit::log_complaint(LSM_NAME, operation, &object);
+        return Ok(());
+    }
+
+    audit::log_denial(LSM_NAME, operation, &object);
+    return_errno_with_message!(errno, "the access is denied by AppArmor");
+}
+
+/// Returns the content of `/proc/[pid]/attr/current`, which describes the profile that confines
+/// the thread.
+pub(crate) fn current_attr(posix_thread: &PosixThread) -> String {
+    let profile = posix_thread.apparmor_context().lock().profile();
+    match profile {
+        Some(profile) => format!("{} ({})\n", profile.name(), profile.data().mode),
+        None => "unconfined\n".to_string(),
+    }
+}
+
+/// Returns the content of `/proc/[pid]/attr/exec`, which describes the profile to switch to on
+/// the next `execve`.
+pub(crate) fn exec_attr(posix_thread: &PosixThread) -> String {
+    let profile = posix_thread.apparmor_context().lock().exec_profile();
+    match profile {
+        Some(profile) => format!("{} ({})\n", profile.name(), profile.data().mode),
+        None => String::new(),
+    }
+}
+
+/// Sets the profile to switch to on the next `execve` of the thread, which must be the current
+/// thread.
+///
+/// `command` is written to `/proc/self/attr/exec` in the format of `exec NAME`.
+pub(crate) fn set_exec_attr(posix_thread: &PosixThread, command: &str) -> Result<()> {
+    let command = command.trim_end_matches(['\n', '\0']);
+    let Some(name) = command.strip_prefix("exec ") else {
+        return_errno_with_message!(Errno::EINVAL, "the command is not supported");
+    };
+
+    // Confined threads would need `change_profile` rules, which are not supported.
+    if posix_thread.apparmor_context().lock().profile().is_some() {
+        return_errno_with_message!(Errno::EPERM, "a confined thread cannot change its profile");
+    }
+    let Some(profile) = policy::find_profile(name.trim()) else {
+        return_errno_with_message!(Errno::ENOENT, "the profile does not exist");
+    };
+
+    posix_thread
+        .apparmor_context()
+        .lock()
+        .set_exec_profile(profile);
+    Ok(())
+}
+
+pub(super) fn init() {
+    let files = vec![
+        SecurityFile::new(".load", String::new).with_store(|text| load_policy(text, false)),
+        SecurityFile::new(".replace", String::new).with_store(|text| load_policy(text, true)),
+        SecurityFile::new(".remove", String::new).with_store(remove_policy),
+        SecurityFile::new("profiles", policy::list_profiles),
+    ];
+
+    if let Err(err) = securityfs::register_dir(SecurityDir::new("apparmor", files)) {
+        warn!("failed to register AppArmor in securityfs: {:?}", err);
+    }
+}
+
+fn load_policy(text: &str, is_replace: bool) -> Result<()> {
+    check_policy_admin()?;
+    policy::load_profiles(text, is_replace)
+}
+
+fn remove_policy(name: &str) -> Result<()> {
+    check_policy_admin()?;
+    policy::remove_profile(name.trim_end_matches(['\n', '\0']))
+}
+
+/// Checks whether the current thread can manage the policy, which requires `CAP_MAC_ADMIN`.
+fn check_policy_admin() -> Result<()> {
+    let current = current_thread!();
+    UserNamespace::get_init_singleton()
+        .check_cap(CapSet::MAC_ADMIN, current.as_posix_thread().unwrap())
+}
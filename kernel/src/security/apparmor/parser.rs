@@ -0,0 +1,380 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The parser of the policy language.
+//!
+//! A policy consists of profiles, each of which is in either of the forms:
+//!
+//! ```text
+//! profile NAME [ATTACHMENT] [flags=(complain)] { RULES }
+//! ATTACHMENT [flags=(complain)] { RULES }
+//! ```
+//!
+//! where the name of a profile in the latter form is its attachment. Each rule ends with a
+//! comma, and it is one of the rules below, optionally prefixed by `deny`:
+//!
+//! ```text
+//! GLOB PERMS,
+//! capability [NAME]...,
+//! network [FAMILY] [TYPE],
+//! ```
+//!
+//! The text after `#` is a comment.
+//!
+//! Reference: <https://manpages.ubuntu.com/manpages/noble/man5/apparmor.d.5.html>.
+
+use core::iter::Peekable;
+
+use super::{
+    glob::Glob,
+    policy::{ExecMode, FilePerms, FileRule, Mode, NetworkRule, ProfileData, Rules},
+};
+use crate::{
+    prelude::*,
+    process::credentials::capabilities::CapSet,
+    util::net::{CSocketAddrFamily, SockType},
+};
+
+/// The names of the capabilities, indexed by the bits in [`CapSet`].
+pub(super) const CAPABILITY_NAMES: [&str; 41] = [
+    "chown",
+    "dac_override",
+    "dac_read_search",
+    "fowner",
+    "fsetid",
+    "kill",
+    "setgid",
+    "setuid",
+    "setpcap",
+    "linux_immutable",
+    "net_bind_service",
+    "net_broadcast",
+    "net_admin",
+    "net_raw",
+    "ipc_lock",
+    "ipc_owner",
+    "sys_module",
+    "sys_rawio",
+    "sys_chroot",
+    "sys_ptrace",
+    "sys_pacct",
+    "sys_admin",
+    "sys_boot",
+    "sys_nice",
+    "sys_resource",
+    "sys_time",
+    "sys_tty_config",
+    "mknod",
+    "lease",
+    "audit_write",
+    "audit_control",
+    "setfcap",
+    "mac_override",
+    "mac_admin",
+    "syslog",
+    "wake_alarm",
+    "block_suspend",
+    "audit_read",
+    "perfmon",
+    "bpf",
+    "checkpoint_restore",
+];
+
+/// The names of the socket families that can be specified in the network rules.
+pub(super) const FAMILY_NAMES: [(&str, CSocketAddrFamily); 5] = [
+    ("unix", CSocketAddrFamily::AF_UNIX),
+    ("inet", CSocketAddrFamily::AF_INET),
+    ("inet6", CSocketAddrFamily::AF_INET6),
+    ("netlink", CSocketAddrFamily::AF_NETLINK),
+    ("packet", CSocketAddrFamily::AF_PACKET),
+];
+
+/// The names of the socket types that can be specified in the network rules.
+pub(super) const TYPE_NAMES: [(&str, SockType); 5] = [
+    ("stream", SockType::SOCK_STREAM),
+    ("dgram", SockType::SOCK_DGRAM),
+    ("raw", SockType::SOCK_RAW),
+    ("rdm", SockType::SOCK_RDM),
+    ("seqpacket", SockType::SOCK_SEQPACKET),
+];
+
+/// Parses the policy, returning the names and the data of the profiles.
+pub(super) fn parse_policy(text: &str) -> Result<Vec<(String, ProfileData)>> {
+    let mut tokens = tokenize(text).into_iter().peekable();
+
+    let mut profiles = Vec::new();
+    while tokens.peek().is_some() {
+        profiles.push(parse_profile(&mut tokens)?);
+    }
+    if profiles.is_empty() {
+        return_errno_with_message!(Errno::EINVAL, "the policy has no profiles");
+    }
+
+    Ok(profiles)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token<'a> {
+    Word(&'a str),
+    /// `,`, which ends a rule.
+    Comma,
+    /// `{`, which starts the rules of a profile.
+    Open,
+    /// `}`, which ends the rules of a profile.
+    Close,
+}
+
+type Tokens<'a> = Peekable<vec::IntoIter<Token<'a>>>;
+
+/// Splits the text into tokens.
+///
+/// A brace that is not followed by a delimiter (e.g., a space) is a part of a glob, so it is not
+/// a token. The commas in the braces (e.g., `/{a,b}`) or in the parentheses (e.g.,
+/// `flags=(complain, audit)`) do not end the word.
+fn tokenize(text: &str) -> Vec<Token<'_>> {
+    let bytes = text.as_bytes();
+    let is_delimiter = |pos: usize| {
+        bytes
+            .get(pos)
+            .is_none_or(|byte| byte.is_ascii_whitespace() || matches!(byte, b',' | b'#'))
+    };
+
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        match bytes[pos] {
+            byte if byte.is_ascii_whitespace() => {
+                pos += 1;
+                continue;
+            }
+            b'#' => {
+                while pos < bytes.len() && bytes[pos] != b'\n' {
+                    pos += 1;
+                }
+                continue;
+            }
+            b',' => {
+                tokens.push(Token::Comma);
+                pos += 1;
+                continue;
+            }
+            b'{' if is_delimiter(pos + 1) => {
+                tokens.push(Token::Open);
+                pos += 1;
+                continue;
+            }
+            b'}' if is_delimiter(pos + 1) => {
+                tokens.push(Token::Close);
+                pos += 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        // The word ends at an ASCII byte or at the end of the text, so it is a valid string.
+        let start = pos;
+        let mut depth = 0usize;
+        while pos < bytes.len() {
+            match bytes[pos] {
+                b'\\' => pos += 1,
+                b'{' | b'(' => depth += 1,
+                b'}' | b')' => depth = depth.saturating_sub(1),
+                b',' if depth == 0 => break,
+                byte if byte.is_ascii_whitespace() && depth == 0 => break,
+                _ => {}
+            }
+            pos += 1;
+        }
+        pos = pos.min(bytes.len());
+        tokens.push(Token::Word(&text[start..pos]));
+    }
+
+    tokens
+}
+
+fn parse_profile(tokens: &mut Tokens) -> Result<(String, ProfileData)> {
+    let mut header = Vec::new();
+    loop {
+        match tokens.next() {
+            Some(Token::Word(word)) => header.push(word),
+            Some(Token::Open) => break,
+            _ => return_errno_with_message!(Errno::EINVAL, "the profile header is invalid"),
+        }
+    }
+    let (name, attachment, mode) = parse_header(&header)?;
+
+    let mut allow = Rules::new();
+    let mut deny = Rules::new();
+    loop {
+        let mut rule = Vec::new();
+        match tokens.next() {
+            Some(Token::Word(word)) => rule.push(word),
+            Some(Token::Close) => break,
+            None => return_errno_with_message!(Errno::EINVAL, "the profile is not closed"),
+            _ => return_errno_with_message!(Errno::EINVAL, "the rule is empty"),
+        }
+        loop {
+            match tokens.next() {
+                Some(Token::Word(word)) => rule.push(word),
+                Some(Token::Comma) => break,
+                _ => {
+                    return_errno_with_message!(Errno::EINVAL, "the rule does not end with a comma")
+                }
+            }
+        }
+
+        match rule.as_slice() {
+            ["deny", rule @ ..] => parse_rule(rule, &mut deny, true)?,
+            ["allow", rule @ ..] => parse_rule(rule, &mut allow, false)?,
+            rule => parse_rule(rule, &mut allow, false)?,
+        }
+    }
+
+    let data = ProfileData {
+        mode,
+        attachment,
+        allow,
+        deny,
+    };
+    Ok((name.to_string(), data))
+}
+
+fn parse_header<'a>(header: &[&'a str]) -> Result<(&'a str, Option<Glob>, Mode)> {
+    let (name, attachment, flags) = match header {
+        ["profile", name, rest @ ..] => match rest {
+            [attachment, flags @ ..] if !attachment.starts_with("flags=") => {
+                (*name, Some(*attachment), flags)
+            }
+            flags => (*name, None, flags),
+        },
+        [name, flags @ ..] if is_glob(name) => (*name, Some(*name), flags),
+        _ => return_errno_with_message!(Errno::EINVAL, "the profile header is invalid"),
+    };
+
+    let attachment = match attachment {
+        Some(attachment) if is_glob(attachment) => Some(Glob::new(attachment)?),
+        Some(_) => return_errno_with_message!(Errno::EINVAL, "the attachment is not a path"),
+        None => None,
+    };
+    let mode = match flags {
+        [] => Mode::Enforce,
+        [flags] => parse_flags(flags)?,
+        _ => return_errno_with_message!(Errno::EINVAL, "the profile header is invalid"),
+    };
+
+    Ok((name, attachment, mode))
+}
+
+fn parse_flags(word: &str) -> Result<Mode> {
+    let Some(flags) = word.strip_prefix("flags=") else {
+        return_errno_with_message!(Errno::EINVAL, "the profile header is invalid");
+    };
+    let flags = flags
+        .strip_prefix('(')
+        .and_then(|flags| flags.strip_suffix(')'))
+        .unwrap_or(flags);
+
+    let mut mode = Mode::Enforce;
+    for flag in flags
+        .split(|char: char| char == ',' || char.is_ascii_whitespace())
+        .filter(|flag| !flag.is_empty())
+    {
+        mode = match flag {
+            "enforce" => Mode::Enforce,
+            "complain" => Mode::Complain,
+            _ => return_errno_with_message!(Errno::EINVAL, "the profile flag is not supported"),
+        };
+    }
+
+    Ok(mode)
+}
+
+fn parse_rule(rule: &[&str], rules: &mut Rules, is_deny: bool) -> Result<()> {
+    match rule {
+        ["capability", names @ ..] => rules.caps |= parse_capabilities(names)?,
+        ["network", qualifiers @ ..] => rules.network.push(parse_network(qualifiers)?),
+        [glob, perms] | [perms, glob] if is_glob(glob) => {
+            rules.files.push(parse_file_rule(glob, perms, is_deny)?)
+        }
+        _ => return_errno_with_message!(Errno::EINVAL, "the rule is not supported"),
+    }
+
+    Ok(())
+}
+
+/// Returns whether the word is a glob of full names, rather than a keyword or permissions.
+fn is_glob(word: &str) -> bool {
+    word.starts_with('/') || word.starts_with('{')
+}
+
+fn parse_capabilities(names: &[&str]) -> Result<CapSet> {
+    if names.is_empty() {
+        return Ok(CapSet::all());
+    }
+
+    names.iter().try_fold(CapSet::empty(), |caps, name| {
+        let Some(bit) = CAPABILITY_NAMES.iter().position(|cap_name| cap_name == name) else {
+            return_errno_with_message!(Errno::EINVAL, "the capability name is invalid");
+        };
+        Ok(caps | CapSet::from_bits_truncate(1 << bit))
+    })
+}
+
+fn parse_network(qualifiers: &[&str]) -> Result<NetworkRule> {
+    let mut rule = NetworkRule {
+        family: None,
+        type_: None,
+    };
+
+    for qualifier in qualifiers {
+        let family = FAMILY_NAMES.iter().find(|(name, _)| name == qualifier);
+        let type_ = TYPE_NAMES.iter().find(|(name, _)| name == qualifier);
+        match (family, type_) {
+            (Some((_, family)), _) if rule.family.is_none() => rule.family = Some(*family),
+            (_, Some((_, type_))) if rule.type_.is_none() => rule.type_ = Some(*type_),
+            _ => return_errno_with_message!(Errno::EINVAL, "the network rule is invalid"),
+        }
+    }
+
+    Ok(rule)
+}
+
+fn parse_file_rule(glob: &str, perms: &str, is_deny: bool) -> Result<FileRule> {
+    let mut file_perms = FilePerms::empty();
+    let mut exec_mode = None;
+
+    let mut chars = perms.chars();
+    while let Some(char) = chars.next() {
+        let perm = match char {
+            'r' => FilePerms::READ,
+            'w' => FilePerms::WRITE,
+            'a' => FilePerms::APPEND,
+            'l' => FilePerms::LINK,
+            'k' => FilePerms::LOCK,
+            'm' => FilePerms::MMAP_EXEC,
+            // The deny rules have no exec modes.
+            'x' if is_deny => FilePerms::EXEC,
+            'i' | 'p' | 'P' | 'u' | 'U' if !is_deny => {
+                if chars.next() != Some('x') || exec_mode.is_some() {
+                    return_errno_with_message!(Errno::EINVAL, "the exec mode is invalid");
+                }
+                exec_mode = Some(match char {
+                    'i' => ExecMode::Inherit,
+                    'p' | 'P' => ExecMode::Profile,
+                    _ => ExecMode::Unconfined,
+                });
+                FilePerms::EXEC
+            }
+            _ => return_errno_with_message!(Errno::EINVAL, "the file permission is invalid"),
+        };
+        file_perms |= perm;
+    }
+    if file_perms.is_empty() {
+        return_errno_with_message!(Errno::EINVAL, "the file rule has no permissions");
+    }
+
+    Ok(FileRule {
+        glob: Glob::new(glob)?,
+        perms: file_perms,
+        exec_mode,
+    })
+}
@@ -0,0 +1,262 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::collections::btree_map::BTreeMap;
+use core::{
+    fmt::{self, Display, Write},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use super::{glob::Glob, parser};
+use crate::{
+    prelude::*,
+    process::credentials::capabilities::CapSet,
+    util::net::{CSocketAddrFamily, SockType},
+};
+
+/// The loaded profiles, indexed by their names.
+static PROFILES: RwLock<BTreeMap<String, Arc<Profile>>> = RwLock::new(BTreeMap::new());
+
+/// A profile, which confines the threads that are attached to it.
+///
+/// When a profile is replaced, the rules are updated in place, so the threads that are attached
+/// to the profile are confined by the new rules. When a profile is removed, the threads that are
+/// attached to the profile become unconfined.
+pub(crate) struct Profile {
+    name: String,
+    data: RwLock<Arc<ProfileData>>,
+    is_removed: AtomicBool,
+}
+
+/// The mode and the rules of a profile.
+pub(super) struct ProfileData {
+    pub(super) mode: Mode,
+    /// The glob that matches the executables to which the profile is attached on `execve`.
+    pub(super) attachment: Option<Glob>,
+    /// The rules that allow accesses.
+    pub(super) allow: Rules,
+    /// The rules that deny accesses, which take precedence over the rules that allow accesses.
+    pub(super) deny: Rules,
+}
+
+/// The mode of a profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Mode {
+    /// The accesses that are not allowed by the profile are denied and logged.
+    Enforce,
+    /// The accesses that are not allowed by the profile are logged but allowed.
+    ///
+    /// The accesses that are explicitly denied by the profile are still denied.
+    Complain,
+}
+
+/// The rules of a profile, which either allow or deny accesses.
+pub(super) struct Rules {
+    pub(super) files: Vec<FileRule>,
+    pub(super) caps: CapSet,
+    pub(super) network: Vec<NetworkRule>,
+}
+
+/// A rule on the files whose full names match the glob.
+pub(super) struct FileRule {
+    pub(super) glob: Glob,
+    pub(super) perms: FilePerms,
+    /// The mode of the transition on `execve`, which is set if and only if the rule allows
+    /// [`FilePerms::EXEC`].
+    pub(super) exec_mode: Option<ExecMode>,
+}
+
+bitflags! {
+    /// The permissions on files.
+    pub(super) struct FilePerms: u32 {
+        /// `r`.
+        const READ      = 1 << 0;
+        /// `w`.
+        const WRITE     = 1 << 1;
+        /// `a`, which is not checked yet, so appending to files requires [`Self::WRITE`].
+        const APPEND    = 1 << 2;
+        /// `l`.
+        const LINK      = 1 << 3;
+        /// `k`, which is not checked yet.
+        const LOCK      = 1 << 4;
+        /// `m`, which is not checked yet.
+        const MMAP_EXEC = 1 << 5;
+        /// `x`.
+        const EXEC      = 1 << 6;
+    }
+}
+
+/// The mode of the transition on `execve`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ExecMode {
+    /// `ix`, where the new program is confined by the same profile.
+    Inherit,
+    /// `px`, where the new program is confined by the profile that is attached to it.
+    Profile,
+    /// `ux`, where the new program is unconfined.
+    Unconfined,
+}
+
+/// A rule on the sockets.
+///
+/// If the family or the type is `None`, the rule applies to all families or types.
+pub(super) struct NetworkRule {
+    pub(super) family: Option<CSocketAddrFamily>,
+    pub(super) type_: Option<SockType>,
+}
+
+impl Profile {
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the current mode and rules of the profile.
+    pub(super) fn data(&self) -> Arc<ProfileData> {
+        self.data.read().clone()
+    }
+
+    /// Returns whether the profile has been removed.
+    pub(crate) fn is_removed(&self) -> bool {
+        self.is_removed.load(Ordering::Relaxed)
+    }
+}
+
+impl Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Enforce => write!(f, "enforce"),
+            Self::Complain => write!(f, "complain"),
+        }
+    }
+}
+
+impl Rules {
+    pub(super) fn new() -> Self {
+        Self {
+            files: Vec::new(),
+            caps: CapSet::empty(),
+            network: Vec::new(),
+        }
+    }
+
+    /// Returns the permissions that the rules grant to the file with the full name.
+    pub(super) fn file_perms(&self, name: &str) -> FilePerms {
+        self.files
+            .iter()
+            .filter(|rule| rule.glob.matches(name))
+            .fold(FilePerms::empty(), |perms, rule| perms | rule.perms)
+    }
+
+    /// Returns the mode of the transition when the file with the full name is executed.
+    pub(super) fn exec_mode(&self, name: &str) -> Option<ExecMode> {
+        self.files
+            .iter()
+            .filter(|rule| rule.exec_mode.is_some())
+            .find(|rule| rule.glob.matches(name))
+            .and_then(|rule| rule.exec_mode)
+    }
+
+    /// Returns whether the rules apply to the sockets with the family and the type.
+    pub(super) fn has_network(&self, family: CSocketAddrFamily, type_: SockType) -> bool {
+        self.network.iter().any(|rule| {
+            rule.family.is_none_or(|rule_family| rule_family == family)
+                && rule.type_.is_none_or(|rule_type| rule_type == type_)
+        })
+    }
+}
+
+impl Display for FilePerms {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const PERM_CHARS: [(FilePerms, char); 7] = [
+            (FilePerms::READ, 'r'),
+            (FilePerms::WRITE, 'w'),
+            (FilePerms::APPEND, 'a'),
+            (FilePerms::LINK, 'l'),
+            (FilePerms::LOCK, 'k'),
+            (FilePerms::MMAP_EXEC, 'm'),
+            (FilePerms::EXEC, 'x'),
+        ];
+
+        for (perm, char) in PERM_CHARS {
+            if self.contains(perm) {
+                write!(f, "{}", char)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Loads the profiles in the policy text.
+///
+/// If `is_replace` is false, loading a profile whose name already exists fails with
+/// [`Errno::EEXIST`]. Either all the profiles are loaded or none of them is loaded.
+pub(super) fn load_profiles(text: &str, is_replace: bool) -> Result<()> {
+    let new_profiles = parser::parse_policy(text)?;
+
+    let mut profiles = PROFILES.write();
+    if !is_replace
+        && new_profiles
+            .iter()
+            .any(|(name, _)| profiles.contains_key(name))
+    {
+        return_errno_with_message!(Errno::EEXIST, "the profile already exists");
+    }
+
+    for (name, data) in new_profiles {
+        if let Some(profile) = profiles.get(&name) {
+            *profile.data.write() = Arc::new(data);
+            continue;
+        }
+
+        let profile = Arc::new(Profile {
+            name: name.clone(),
+            data: RwLock::new(Arc::new(data)),
+            is_removed: AtomicBool::new(false),
+        });
+        profiles.insert(name, profile);
+    }
+
+    Ok(())
+}
+
+/// Removes the profile with the name.
+pub(super) fn remove_profile(name: &str) -> Result<()> {
+    let Some(profile) = PROFILES.write().remove(name) else {
+        return_errno_with_message!(Errno::ENOENT, "the profile does not exist");
+    };
+    profile.is_removed.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Finds the profile with the name.
+pub(super) fn find_profile(name: &str) -> Option<Arc<Profile>> {
+    PROFILES.read().get(name).cloned()
+}
+
+/// Finds the profile that is attached to the executable with the full name.
+///
+/// If multiple profiles match, the one whose attachment is the longest (and thus usually the
+/// most specific) is chosen.
+pub(super) fn find_attached_profile(name: &str) -> Option<Arc<Profile>> {
+    let profiles = PROFILES.read();
+    profiles
+        .values()
+        .filter_map(|profile| {
+            let data = profile.data();
+            let attachment = data.attachment.as_ref()?;
+            attachment
+                .matches(name)
+                .then(|| (attachment.as_str().len(), profile))
+        })
+        .max_by_key(|(len, _)| *len)
+        .map(|(_, profile)| profile.clone())
+}
+
+/// Lists the loaded profiles, one per line, in the format of `name (mode)`.
+pub(super) fn list_profiles() -> String {
+    let profiles = PROFILES.read();
+    profiles.values().fold(String::new(), |mut list, profile| {
+        // Writing to a string never fails.
+        writeln!(list, "{} ({})", profile.name, profile.data().mode).unwrap();
+        list
+    })
+}
@@ -0,0 +1,344 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The configuration of the audit subsystem, which is set by the audit daemon.
+
+use alloc::format;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use super::{
+    AUDIT_UID_UNSET, current_event_id,
+    record::{self, RecordType},
+    task::TaskInfo,
+};
+use crate::{prelude::*, process::Pid, time::clocks::MonotonicClock};
+
+/// `audit_status` in Linux.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/audit.h#L465>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(crate) struct CAuditStatus {
+    /// The fields that are valid, which is [`StatusMask`].
+    mask: u32,
+    enabled: u32,
+    failure: u32,
+    pid: u32,
+    rate_limit: u32,
+    backlog_limit: u32,
+    lost: u32,
+    backlog: u32,
+    feature_bitmap: u32,
+    backlog_wait_time: u32,
+    backlog_wait_time_actual: u32,
+}
+
+bitflags! {
+    /// The fields of [`CAuditStatus`] that are valid.
+    struct StatusMask: u32 {
+        const ENABLED                  = 0x0001;
+        const FAILURE                  = 0x0002;
+        const PID                      = 0x0004;
+        const RATE_LIMIT               = 0x0008;
+        const BACKLOG_LIMIT            = 0x0010;
+        const BACKLOG_WAIT_TIME        = 0x0020;
+        const LOST                     = 0x0040;
+        const BACKLOG_WAIT_TIME_ACTUAL = 0x0080;
+    }
+}
+
+bitflags! {
+    /// The features that are supported.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/audit.h#L365>.
+    struct FeatureBitmap: u32 {
+        const BACKLOG_LIMIT     = 0x0001;
+        const BACKLOG_WAIT_TIME = 0x0002;
+    }
+}
+
+/// Whether the audit subsystem is enabled.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+enum EnabledState {
+    Disabled = 0,
+    Enabled = 1,
+    /// The audit subsystem is enabled, and the configuration and the rules cannot be changed
+    /// until reboot.
+    Locked = 2,
+}
+
+/// The action that is taken if records are lost.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+enum FailureAction {
+    Silent = 0,
+    Printk = 1,
+    Panic = 2,
+}
+
+/// The default backlog wait time, which is 60 seconds in jiffies.
+///
+/// The backlog wait time is not used (see [`AuditConfig::backlog_limit`]), so the values assume
+/// that there are 1000 jiffies per second.
+const DEFAULT_BACKLOG_WAIT_TIME: u32 = 60 * 1000;
+/// The maximum backlog wait time, which is ten times the default one.
+const MAX_BACKLOG_WAIT_TIME: u32 = 10 * DEFAULT_BACKLOG_WAIT_TIME;
+
+struct AuditConfig {
+    enabled: EnabledState,
+    failure: FailureAction,
+    daemon: Option<AuditDaemon>,
+    /// The maximum number of the records per second, or zero if there is no limit.
+    rate_limit: u32,
+    /// The maximum number of the records that are waiting for the audit daemon.
+    ///
+    /// The records are queued in the socket of the audit daemon, whose buffer size is limited, so
+    /// this value is not used.
+    backlog_limit: u32,
+    backlog_wait_time: u32,
+    /// The second of the current rate limit window and the number of the records in it.
+    rate_window: (u64, u32),
+}
+
+/// The audit daemon that receives the records.
+struct AuditDaemon {
+    pid: Pid,
+    port: u32,
+}
+
+static CONFIG: SpinLock<AuditConfig> = SpinLock::new(AuditConfig {
+    enabled: EnabledState::Disabled,
+    failure: FailureAction::Printk,
+    daemon: None,
+    rate_limit: 0,
+    backlog_limit: 64,
+    backlog_wait_time: DEFAULT_BACKLOG_WAIT_TIME,
+    rate_window: (0, 0),
+});
+
+/// Whether the audit subsystem is enabled, which mirrors [`AuditConfig::enabled`].
+static IS_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// The number of the records that are lost.
+static LOST: AtomicU32 = AtomicU32::new(0);
+
+/// Returns whether the audit subsystem is enabled.
+pub(super) fn is_enabled() -> bool {
+    IS_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Returns whether the configuration and the rules are locked.
+pub(super) fn is_locked() -> bool {
+    CONFIG.lock().enabled == EnabledState::Locked
+}
+
+/// Returns the current status of the audit subsystem.
+pub(crate) fn status() -> CAuditStatus {
+    let config = CONFIG.lock();
+    CAuditStatus {
+        mask: 0,
+        enabled: config.enabled as u32,
+        failure: config.failure as u32,
+        pid: config.daemon.as_ref().map_or(0, |daemon| daemon.pid),
+        rate_limit: config.rate_limit,
+        backlog_limit: config.backlog_limit,
+        lost: LOST.load(Ordering::Relaxed),
+        backlog: 0,
+        feature_bitmap: FeatureBitmap::all().bits(),
+        backlog_wait_time: config.backlog_wait_time,
+        backlog_wait_time_actual: 0,
+    }
+}
+
+/// Changes the status of the audit subsystem.
+///
+/// Only the fields in [`CAuditStatus::mask`] are changed. If the audit daemon is registered (or
+/// unregistered), its netlink port is `port`.
+pub(crate) fn set_status(status: &CAuditStatus, port: u32) -> Result<()> {
+    let mask = StatusMask::from_bits_truncate(status.mask);
+
+    if mask.contains(StatusMask::ENABLED) {
+        let new_state = EnabledState::try_from(status.enabled)
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the enabled state is invalid"))?;
+        change_config("audit_enabled", status.enabled, |config| {
+            let old = config.enabled as u32;
+            config.enabled = new_state;
+            IS_ENABLED.store(new_state != EnabledState::Disabled, Ordering::Relaxed);
+            old
+        })?;
+    }
+
+    if mask.contains(StatusMask::FAILURE) {
+        let new_action = FailureAction::try_from(status.failure)
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the failure action is invalid"))?;
+        change_config("audit_failure", status.failure, |config| {
+            let old = config.failure as u32;
+            config.failure = new_action;
+            old
+        })?;
+    }
+
+    if mask.contains(StatusMask::PID) {
+        set_daemon(status.pid, port)?;
+    }
+
+    if mask.contains(StatusMask::RATE_LIMIT) {
+        change_config("audit_rate_limit", status.rate_limit, |config| {
+            core::mem::replace(&mut config.rate_limit, status.rate_limit)
+        })?;
+    }
+
+    if mask.contains(StatusMask::BACKLOG_LIMIT) {
+        change_config("audit_backlog_limit", status.backlog_limit, |config| {
+            core::mem::replace(&mut config.backlog_limit, status.backlog_limit)
+        })?;
+    }
+
+    if mask.contains(StatusMask::BACKLOG_WAIT_TIME) {
+        if status.backlog_wait_time > MAX_BACKLOG_WAIT_TIME {
+            return_errno_with_message!(Errno::EINVAL, "the backlog wait time is too long");
+        }
+        change_config("audit_backlog_wait_time", status.backlog_wait_time, |config| {
+            core::mem::replace(&mut config.backlog_wait_time, status.backlog_wait_time)
+        })?;
+    }
+
+    if mask.contains(StatusMask::LOST) {
+        let old = LOST.swap(0, Ordering::Relaxed);
+        if is_enabled() {
+            log_config_change("lost", 0, old, true);
+        }
+    }
+
+    Ok(())
+}
+
+/// Changes a field of the configuration with the closure, which returns the old value.
+///
+/// Like Linux, the change is recorded if the audit subsystem is enabled. The change fails with
+/// [`Errno::EPERM`] if the configuration is locked.
+fn change_config(name: &str, new: u32, change: impl FnOnce(&mut AuditConfig) -> u32) -> Result<()> {
+    let mut config = CONFIG.lock();
+    let was_enabled = config.enabled != EnabledState::Disabled;
+    let is_allowed = config.enabled != EnabledState::Locked;
+    let old = if is_allowed {
+        change(&mut config)
+    } else {
+        new
+    };
+    drop(config);
+
+    if was_enabled {
+        log_config_change(name, new, old, is_allowed);
+    }
+
+    if !is_allowed {
+        return_errno_with_message!(Errno::EPERM, "the audit configuration is locked");
+    }
+    Ok(())
+}
+
+/// Registers or unregisters (if `new_pid` is zero) the audit daemon.
+fn set_daemon(new_pid: Pid, port: u32) -> Result<()> {
+    let current_pid = current!().pid();
+    if new_pid != 0 && new_pid != current_pid {
+        return_errno_with_message!(Errno::EINVAL, "the audit daemon must register itself");
+    }
+
+    let mut config = CONFIG.lock();
+    let was_enabled = config.enabled != EnabledState::Disabled;
+    let old_pid = config.daemon.as_ref().map_or(0, |daemon| daemon.pid);
+    if config.enabled == EnabledState::Locked {
+        return_errno_with_message!(Errno::EPERM, "the audit configuration is locked");
+    }
+    if new_pid != 0 && old_pid != 0 && old_pid != new_pid {
+        return_errno_with_message!(Errno::EEXIST, "another audit daemon is registered");
+    }
+    if new_pid == 0 && old_pid != 0 && old_pid != current_pid {
+        return_errno_with_message!(Errno::EACCES, "only the audit daemon can unregister itself");
+    }
+
+    config.daemon = if new_pid != 0 {
+        Some(AuditDaemon { pid: new_pid, port })
+    } else {
+        None
+    };
+    drop(config);
+
+    if was_enabled {
+        log_config_change("audit_pid", new_pid, old_pid, true);
+    }
+    Ok(())
+}
+
+/// Returns the netlink port of the audit daemon, if it is registered.
+pub(super) fn daemon_port() -> Option<u32> {
+    CONFIG.lock().daemon.as_ref().map(|daemon| daemon.port)
+}
+
+/// Unregisters the audit daemon whose netlink port is `port`, which no longer exists.
+pub(super) fn reset_daemon(port: u32) {
+    let mut config = CONFIG.lock();
+    if config
+        .daemon
+        .as_ref()
+        .is_some_and(|daemon| daemon.port == port)
+    {
+        config.daemon = None;
+    }
+}
+
+/// Checks whether one more record can be emitted without exceeding the rate limit.
+pub(super) fn check_rate_limit() -> bool {
+    let mut config = CONFIG.lock();
+    if config.rate_limit == 0 {
+        return true;
+    }
+
+    let now = MonotonicClock::get().read_time().as_secs();
+    let (window, count) = &mut config.rate_window;
+    if *window != now {
+        *window = now;
+        *count = 0;
+    }
+    if *count >= config.rate_limit {
+        return false;
+    }
+    *count += 1;
+    true
+}
+
+/// Reports that a record is lost, and takes the failure action.
+pub(super) fn report_lost(reason: &str) {
+    let lost = LOST.fetch_add(1, Ordering::Relaxed) + 1;
+
+    let config = CONFIG.lock();
+    let failure = config.failure;
+    let rate_limit = config.rate_limit;
+    let backlog_limit = config.backlog_limit;
+    drop(config);
+
+    match failure {
+        FailureAction::Silent => (),
+        FailureAction::Printk => warn!(
+            "audit: {}: audit_lost={} audit_rate_limit={} audit_backlog_limit={}",
+            reason, lost, rate_limit, backlog_limit
+        ),
+        FailureAction::Panic => panic!("audit: {}", reason),
+    }
+}
+
+/// Records the change of the configuration.
+fn log_config_change(name: &str, new: u32, old: u32, is_allowed: bool) {
+    let task = TaskInfo::current();
+    let text = format!(
+        "op=set {}={} old={} auid={} ses={} res={}",
+        name, new, old, AUDIT_UID_UNSET, AUDIT_UID_UNSET, is_allowed as u8
+    );
+    record::emit(
+        RecordType::ConfigChange as u16,
+        current_event_id(),
+        task.as_ref(),
+        &text,
+    );
+}
@@ -0,0 +1,51 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::record::{EventId, RecordType};
+use crate::prelude::*;
+
+/// The audit state of a thread.
+///
+/// The state collects the records of the current system call, which are emitted when the system
+/// call exits if the system call is audited.
+#[derive(Default)]
+pub(crate) struct AuditContext {
+    pub(super) syscall: Option<SyscallInfo>,
+    /// The ID of the event, which is allocated when the first record of the event is emitted.
+    pub(super) event_id: Option<EventId>,
+    /// Whether the system call is audited regardless of the rules.
+    ///
+    /// This is set if a record has been emitted during the system call (e.g., the record of an
+    /// access denial), so that the record is accompanied by the record of the system call.
+    pub(super) is_forced: bool,
+    /// The records that are emitted along with the record of the system call.
+    pub(super) aux_records: Vec<(RecordType, String)>,
+}
+
+/// A system call that is being audited.
+pub(super) struct SyscallInfo {
+    pub(super) number: u64,
+    pub(super) args: [u64; 4],
+    /// The return value, which is valid only after the system call exits.
+    pub(super) ret: i64,
+}
+
+impl AuditContext {
+    /// Returns the ID of the event of the current system call, and forces the system call to be
+    /// audited.
+    ///
+    /// This method returns `None` if the thread is not in a system call that can be audited.
+    pub(super) fn force_event_id(&mut self) -> Option<EventId> {
+        self.syscall.as_ref()?;
+        self.is_forced = true;
+        Some(*self.event_id.get_or_insert_with(EventId::new))
+    }
+
+    /// Adds a record that is emitted along with the record of the current system call.
+    ///
+    /// The record is discarded if the thread is not in a system call that can be audited.
+    pub(super) fn push_aux_record(&mut self, type_: RecordType, text: String) {
+        if self.syscall.is_some() {
+            self.aux_records.push((type_, text));
+        }
+    }
+}
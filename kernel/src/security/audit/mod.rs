@@ -0,0 +1,326 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The audit subsystem.
+//!
+//! The audit subsystem records security-relevant events, such as system calls and access denials
+//! of security modules. The records are sent to the audit daemon (e.g., `auditd`) via
+//! `NETLINK_AUDIT` sockets, through which the daemon also configures the subsystem and the rules.
+//! If no audit daemon is registered, the records are written to the kernel log instead.
+//!
+//! When the audit subsystem is enabled, the rules decide which system calls are audited. The
+//! records of a system call (e.g., the arguments of `execve`) are collected while the system call
+//! runs, and they are emitted with the same event ID when the system call exits.
+//!
+//! Reference: <https://man7.org/linux/man-pages/man7/audit.rules.7.html>.
+
+mod config;
+mod context;
+mod record;
+mod rule;
+mod task;
+
+use alloc::format;
+use core::fmt::{Display, Write};
+
+use ostd::task::Task;
+
+pub(crate) use self::{
+    config::{CAuditStatus, set_status, status},
+    context::AuditContext,
+    record::UntrustedStr,
+    rule::{AuditRule, FilterList},
+};
+use self::{
+    context::SyscallInfo,
+    record::{EventId, KeyField, RecordType},
+    rule::{MatchedEvent, RuleAction},
+    task::TaskInfo,
+};
+use crate::prelude::*;
+
+/// The architecture of the system calls, which is `AUDIT_ARCH_*` in Linux.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/audit.h#L387>.
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH: u32 = 0xC000_003E;
+#[cfg(target_arch = "riscv64")]
+const AUDIT_ARCH: u32 = 0xC000_00F3;
+#[cfg(target_arch = "loongarch64")]
+const AUDIT_ARCH: u32 = 0xC000_0102;
+
+/// The user ID (or the session ID) that is not set.
+///
+/// Login UIDs and sessions are not supported, so they are always unset.
+const AUDIT_UID_UNSET: u32 = u32::MAX;
+
+/// The type of the messages from user space that report access denials.
+const AUDIT_USER_AVC: u16 = 1107;
+
+/// The maximum length of the messages from user space.
+const AUDIT_MESSAGE_TEXT_MAX: usize = 8560;
+
+/// The audit rules of all the lists, in the order in which they are checked.
+static RULES: RwLock<Vec<AuditRule>> = RwLock::new(Vec::new());
+
+fn rules() -> &'static RwLock<Vec<AuditRule>> {
+    &RULES
+}
+
+/// Starts auditing the system call that the current thread enters.
+pub(crate) fn syscall_entry(ctx: &Context, number: u64, args: &[u64; 6]) {
+    if !config::is_enabled() {
+        return;
+    }
+
+    let mut audit_context = ctx.thread_local.audit_context().borrow_mut();
+    audit_context.syscall = Some(SyscallInfo {
+        number,
+        args: [args[0], args[1], args[2], args[3]],
+        ret: 0,
+    });
+}
+
+/// Finishes auditing the system call that the current thread exits.
+///
+/// If the system call is audited, its records are emitted.
+pub(crate) fn syscall_exit(ctx: &Context, ret: i64) {
+    let AuditContext {
+        syscall,
+        event_id,
+        is_forced,
+        aux_records,
+    } = core::mem::take(&mut *ctx.thread_local.audit_context().borrow_mut());
+    let Some(mut syscall) = syscall else {
+        return;
+    };
+    syscall.ret = ret;
+
+    let Some(task) = TaskInfo::current() else {
+        return;
+    };
+
+    let mut is_audited = is_forced;
+    let mut key = None;
+    {
+        let rules = rules().read();
+        let event = MatchedEvent {
+            task: &task,
+            syscall: Some(&syscall),
+            msg_type: None,
+        };
+        let find_rule = |list: FilterList| {
+            rules
+                .iter()
+                .find(|rule| rule.list() == list && rule.matches(&event))
+        };
+
+        if let Some(rule) = find_rule(FilterList::Task) {
+            if rule.action() == RuleAction::Never {
+                return;
+            }
+            is_audited = true;
+            key = rule.key().map(ToString::to_string);
+        }
+        // The rules of the exit list override the rules of the task list.
+        if let Some(rule) = find_rule(FilterList::Exit) {
+            is_audited = is_forced || rule.action() == RuleAction::Always;
+            key = rule.key().map(ToString::to_string);
+        }
+    }
+    if !is_audited {
+        return;
+    }
+
+    let event_id = event_id.unwrap_or_else(EventId::new);
+    let text = format!(
+        "arch={:x} syscall={} success={} exit={} a0={:x} a1={:x} a2={:x} a3={:x} items=0 {} {} \
+         key={}",
+        AUDIT_ARCH,
+        syscall.number,
+        if ret >= 0 { "yes" } else { "no" },
+        ret,
+        syscall.args[0],
+        syscall.args[1],
+        syscall.args[2],
+        syscall.args[3],
+        task.ids(),
+        task.executable(),
+        KeyField(key.as_deref())
+    );
+    record::emit(RecordType::Syscall as u16, event_id, Some(&task), &text);
+
+    for (type_, text) in aux_records {
+        record::emit(type_ as u16, event_id, Some(&task), &text);
+    }
+    record::emit(RecordType::Eoe as u16, event_id, Some(&task), &"");
+}
+
+/// Records the arguments of `execve`, which is being called by the current thread.
+pub(crate) fn log_execve(ctx: &Context, argv: &[CString]) {
+    let mut audit_context = ctx.thread_local.audit_context().borrow_mut();
+    if audit_context.syscall.is_none() {
+        return;
+    }
+
+    let mut text = format!("argc={}", argv.len());
+    for (i, arg) in argv.iter().enumerate() {
+        // Writing to a string never fails.
+        write!(text, " a{}={}", i, UntrustedStr(arg.as_bytes())).unwrap();
+    }
+    audit_context.push_aux_record(RecordType::Execve, text);
+}
+
+/// Records that a security module denies an access of the current thread.
+///
+/// `object` describes the object of the access, such as `opid=1 ocomm="init"`.
+pub(crate) fn log_denial(lsm: &str, operation: &str, object: &dyn Display) {
+    log_access(lsm, operation, "denied", object);
+}
+
+/// Records that a security module would deny an access of the current thread, but allows it
+/// because the security module only complains about the access.
+///
+/// `object` describes the object of the access, as in [`log_denial`].
+pub(crate) fn log_complaint(lsm: &str, operation: &str, object: &dyn Display) {
+    log_access(lsm, operation, "allowed", object);
+}
+
+fn log_access(lsm: &str, operation: &str, result: &str, object: &dyn Display) {
+    let task = TaskInfo::current();
+    let event_id = current_event_id();
+
+    let text = match task.as_ref() {
+        Some(task) => format!(
+            "lsm={} op={} res={} pid={} {} {}",
+            lsm,
+            operation,
+            result,
+            task.pid,
+            task.executable(),
+            object
+        ),
+        None => format!("lsm={} op={} res={} {}", lsm, operation, result, object),
+    };
+    record::emit(RecordType::Avc as u16, event_id, task.as_ref(), &text);
+}
+
+/// Records a message sent from user space by the current thread.
+pub(crate) fn log_user_message(type_: u16, message: &[u8]) {
+    if !config::is_enabled() && type_ != AUDIT_USER_AVC {
+        return;
+    }
+
+    let Some(task) = TaskInfo::current() else {
+        return;
+    };
+
+    {
+        let event = MatchedEvent {
+            task: &task,
+            syscall: None,
+            msg_type: Some(type_),
+        };
+        let rules = rules().read();
+        let matched_rule = rules
+            .iter()
+            .find(|rule| rule.list() == FilterList::User && rule.matches(&event));
+        if matched_rule.is_some_and(|rule| rule.action() == RuleAction::Never) {
+            return;
+        }
+    }
+
+    // Like Linux, the message is not escaped, but it ends at the first NUL byte.
+    let message = message.split(|byte| *byte == 0).next().unwrap();
+    let message = &message[..message.len().min(AUDIT_MESSAGE_TEXT_MAX)];
+    let text = format!(
+        "pid={} uid={} auid={} ses={} msg='{}'",
+        task.pid,
+        task.uid,
+        AUDIT_UID_UNSET,
+        AUDIT_UID_UNSET,
+        String::from_utf8_lossy(message)
+    );
+    record::emit(type_, current_event_id(), Some(&task), &text);
+}
+
+/// Adds the rule to its list.
+///
+/// If `is_prepend` is true, the rule is checked before the existing rules in the list.
+pub(crate) fn add_rule(rule: AuditRule, is_prepend: bool) -> Result<()> {
+    if config::is_locked() {
+        return_errno_with_message!(Errno::EPERM, "the audit rules are locked");
+    }
+
+    let mut rules = rules().write();
+    if rules.contains(&rule) {
+        return_errno_with_message!(Errno::EEXIST, "the audit rule already exists");
+    }
+    let key = rule.key().map(ToString::to_string);
+    let list = rule.list();
+    if is_prepend {
+        rules.insert(0, rule);
+    } else {
+        rules.push(rule);
+    }
+    drop(rules);
+
+    log_rule_change("add_rule", key.as_deref(), list);
+    Ok(())
+}
+
+/// Deletes the rule that is the same as `rule`.
+pub(crate) fn del_rule(rule: &AuditRule) -> Result<()> {
+    if config::is_locked() {
+        return_errno_with_message!(Errno::EPERM, "the audit rules are locked");
+    }
+
+    let mut rules = rules().write();
+    let Some(index) = rules.iter().position(|existing| existing == rule) else {
+        return_errno_with_message!(Errno::ENOENT, "the audit rule does not exist");
+    };
+    rules.remove(index);
+    drop(rules);
+
+    log_rule_change("remove_rule", rule.key(), rule.list());
+    Ok(())
+}
+
+/// Returns the bytes of `audit_rule_data` that describe each rule.
+pub(crate) fn list_rules() -> Vec<Vec<u8>> {
+    rules().read().iter().map(AuditRule::to_bytes).collect()
+}
+
+/// Records the change of the rules.
+fn log_rule_change(operation: &str, key: Option<&str>, list: FilterList) {
+    if !config::is_enabled() {
+        return;
+    }
+
+    let task = TaskInfo::current();
+    let text = format!(
+        "auid={} ses={} op={} key={} list={} res=1",
+        AUDIT_UID_UNSET,
+        AUDIT_UID_UNSET,
+        operation,
+        KeyField(key),
+        list as u32
+    );
+    record::emit(
+        RecordType::ConfigChange as u16,
+        current_event_id(),
+        task.as_ref(),
+        &text,
+    );
+}
+
+/// Returns the ID of the event that the current thread causes.
+///
+/// Like Linux, if the current thread is in a system call, the event is the event of the system
+/// call, so the system call will also be recorded.
+fn current_event_id() -> EventId {
+    Task::current()
+        .as_ref()
+        .and_then(|task| task.as_thread_local())
+        .and_then(|thread_local| thread_local.audit_context().borrow_mut().force_event_id())
+        .unwrap_or_else(EventId::new)
+}
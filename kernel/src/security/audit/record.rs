@@ -0,0 +1,157 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The audit records and how they are emitted.
+
+use alloc::format;
+use core::{
+    fmt::{self, Display},
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+
+use super::{
+    config,
+    rule::{FilterList, MatchedEvent},
+    rules,
+    task::TaskInfo,
+};
+use crate::{net::socket::netlink::send_audit_record, prelude::*, time::clocks::RealTimeClock};
+
+/// The types of the records that are generated by the kernel.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/audit.h#L100>.
+#[repr(u16)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum RecordType {
+    /// A system call.
+    Syscall = 1300,
+    /// A change of the configuration or the rules.
+    ConfigChange = 1305,
+    /// The arguments of `execve`.
+    Execve = 1309,
+    /// The end of a multi-record event.
+    Eoe = 1320,
+    /// An access that is denied by a security module.
+    Avc = 1400,
+}
+
+/// The serial number of the last event.
+static LAST_SERIAL: AtomicU32 = AtomicU32::new(0);
+
+/// The timestamp and the serial number that identify an event.
+///
+/// All the records of an event have the same ID, so that user space can put them together.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct EventId {
+    time: Duration,
+    serial: u32,
+}
+
+impl EventId {
+    /// Creates the ID of a new event, which happens now.
+    pub(super) fn new() -> Self {
+        Self {
+            time: RealTimeClock::get().read_time(),
+            serial: LAST_SERIAL.fetch_add(1, Ordering::Relaxed).wrapping_add(1),
+        }
+    }
+}
+
+impl Display for EventId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "audit({}.{:03}:{})",
+            self.time.as_secs(),
+            self.time.subsec_millis(),
+            self.serial
+        )
+    }
+}
+
+/// A string that may be controlled by user space.
+///
+/// Like Linux, the string is quoted if it contains only printable characters other than spaces
+/// and double quotes. Otherwise, it is hex-encoded, so that it cannot forge other fields.
+pub(crate) struct UntrustedStr<'a>(pub(crate) &'a [u8]);
+
+impl Display for UntrustedStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let needs_hex = self
+            .0
+            .iter()
+            .any(|byte| *byte == b'"' || *byte < 0x21 || *byte > 0x7e);
+        if !needs_hex {
+            // The bytes are all printable ASCII characters.
+            let str = core::str::from_utf8(self.0).unwrap();
+            return write!(f, "\"{}\"", str);
+        }
+
+        for byte in self.0.iter() {
+            write!(f, "{:02X}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// The key of the matched rule in an audit record, which is `(null)` if there is no key.
+pub(super) struct KeyField<'a>(pub(super) Option<&'a str>);
+
+impl Display for KeyField<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(key) => write!(f, "{}", UntrustedStr(key.as_bytes())),
+            None => write!(f, "(null)"),
+        }
+    }
+}
+
+/// Emits a record.
+///
+/// The record is sent to the audit daemon and the listeners of the audit multicast group. If no
+/// audit daemon is registered, the record is written to the kernel log instead.
+///
+/// The record is dropped if it is excluded by the rules of [`FilterList::Exclude`] or if the rate
+/// limit is exceeded.
+pub(super) fn emit(type_: u16, id: EventId, task: Option<&TaskInfo>, text: &dyn Display) {
+    if is_excluded(type_, task) {
+        return;
+    }
+    if !config::check_rate_limit() {
+        config::report_lost("rate limit exceeded");
+        return;
+    }
+
+    let payload = format!("{}: {}", id, text);
+
+    let daemon_port = config::daemon_port();
+    if send_audit_record(daemon_port, type_, &payload) {
+        return;
+    }
+
+    if let Some(port) = daemon_port {
+        // The audit daemon has exited without unregistering itself.
+        config::reset_daemon(port);
+    }
+    info!("audit: type={} {}", type_, payload);
+}
+
+/// Returns whether the record of the type is excluded by the rules.
+fn is_excluded(type_: u16, task: Option<&TaskInfo>) -> bool {
+    // Records that are not caused by any threads are never excluded.
+    let Some(task) = task else {
+        return false;
+    };
+
+    let event = MatchedEvent {
+        task,
+        syscall: None,
+        msg_type: Some(type_),
+    };
+    // Like Linux, any matched rule excludes the record, regardless of its action.
+    rules()
+        .read()
+        .iter()
+        .filter(|rule| rule.list() == FilterList::Exclude)
+        .any(|rule| rule.matches(&event))
+}
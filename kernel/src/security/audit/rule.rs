@@ -0,0 +1,347 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The audit rules, which decide which events are audited.
+
+use super::{AUDIT_ARCH, AUDIT_UID_UNSET, context::SyscallInfo, task::TaskInfo};
+use crate::prelude::*;
+
+/// The number of `u32` words in the mask of the system calls.
+const AUDIT_BITMASK_SIZE: usize = 64;
+/// The maximum number of the fields in a rule.
+const AUDIT_MAX_FIELDS: usize = 64;
+/// The maximum length of the filter key.
+const AUDIT_MAX_KEY_LEN: usize = 256;
+
+/// The flag that adds the rule to the head of the list, instead of the tail.
+const AUDIT_FILTER_PREPEND: u32 = 0x10;
+
+/// `audit_rule_data` in Linux, without the trailing buffer of the string fields.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/audit.h#L511>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CAuditRuleData {
+    flags: u32,
+    action: u32,
+    field_count: u32,
+    mask: [u32; AUDIT_BITMASK_SIZE],
+    fields: [u32; AUDIT_MAX_FIELDS],
+    values: [u32; AUDIT_MAX_FIELDS],
+    fieldflags: [u32; AUDIT_MAX_FIELDS],
+    buflen: u32,
+}
+
+/// The list of the rules, which decides when the rules are checked.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+pub(crate) enum FilterList {
+    /// The rules that filter the messages sent from user space.
+    User = 0x00,
+    /// The rules that are checked against the thread at each system call.
+    ///
+    /// Unlike Linux, where the rules are checked only when a thread is created, the rules are
+    /// checked at the exit of each system call. This makes no difference unless the rules are
+    /// changed after the thread is created.
+    Task = 0x01,
+    /// The rules that are checked at the exit of each system call.
+    Exit = 0x04,
+    /// The rules that exclude the records of some types.
+    Exclude = 0x05,
+}
+
+/// The action that is taken if a rule matches.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+pub(crate) enum RuleAction {
+    /// The event is not audited.
+    Never = 0,
+    /// The event is audited.
+    Always = 2,
+}
+
+/// The types of the fields of the rules.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/audit.h#L244>.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+enum FieldType {
+    Pid = 0,
+    Uid = 1,
+    Euid = 2,
+    Suid = 3,
+    Fsuid = 4,
+    Gid = 5,
+    Egid = 6,
+    Sgid = 7,
+    Fsgid = 8,
+    LoginUid = 9,
+    Pers = 10,
+    Arch = 11,
+    MsgType = 12,
+    Ppid = 18,
+    LoginUidSet = 24,
+    SessionId = 25,
+    Exit = 103,
+    Success = 104,
+    Arg0 = 200,
+    Arg1 = 201,
+    Arg2 = 202,
+    Arg3 = 203,
+    FilterKey = 210,
+}
+
+impl FieldType {
+    /// Returns whether the field can be used in the rules of the list.
+    fn is_allowed_in(self, list: FilterList) -> bool {
+        match self {
+            Self::MsgType => matches!(list, FilterList::User | FilterList::Exclude),
+            Self::Exit | Self::Success | Self::Arg0 | Self::Arg1 | Self::Arg2 | Self::Arg3 => {
+                list == FilterList::Exit
+            }
+            _ => true,
+        }
+    }
+
+    /// Returns the value of the field for the event, or `None` if the event has no such field.
+    fn value_of(self, event: &MatchedEvent) -> Option<u32> {
+        let task = event.task;
+        let syscall = event.syscall;
+
+        let value = match self {
+            Self::Pid => task.pid,
+            Self::Uid => task.uid,
+            Self::Euid => task.euid,
+            Self::Suid => task.suid,
+            Self::Fsuid => task.fsuid,
+            Self::Gid => task.gid,
+            Self::Egid => task.egid,
+            Self::Sgid => task.sgid,
+            Self::Fsgid => task.fsgid,
+            // TODO: Support login UIDs and sessions, which are set via `/proc/self/loginuid`.
+            Self::LoginUid | Self::SessionId => AUDIT_UID_UNSET,
+            Self::LoginUidSet => 0,
+            // TODO: Support personalities.
+            Self::Pers => 0,
+            Self::Arch => AUDIT_ARCH,
+            Self::MsgType => event.msg_type? as u32,
+            Self::Ppid => task.ppid,
+            // Like Linux, the values are compared as `u32`s, so negative error codes should be
+            // specified as their two's complements.
+            Self::Exit => syscall?.ret as u32,
+            Self::Success => {
+                if syscall?.ret >= 0 {
+                    AUDITSC_SUCCESS
+                } else {
+                    AUDITSC_FAILURE
+                }
+            }
+            Self::Arg0 => syscall?.args[0] as u32,
+            Self::Arg1 => syscall?.args[1] as u32,
+            Self::Arg2 => syscall?.args[2] as u32,
+            Self::Arg3 => syscall?.args[3] as u32,
+            Self::FilterKey => return None,
+        };
+
+        Some(value)
+    }
+}
+
+/// The value of [`FieldType::Success`] if the system call succeeds.
+const AUDITSC_SUCCESS: u32 = 1;
+/// The value of [`FieldType::Success`] if the system call fails.
+const AUDITSC_FAILURE: u32 = 2;
+
+/// The operators that compare the fields with the values.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/audit.h#L340>.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+enum Operator {
+    BitMask = 0x0800_0000,
+    LessThan = 0x1000_0000,
+    GreaterThan = 0x2000_0000,
+    NotEqual = 0x3000_0000,
+    Equal = 0x4000_0000,
+    BitTest = 0x4800_0000,
+    LessThanOrEqual = 0x5000_0000,
+    GreaterThanOrEqual = 0x6000_0000,
+}
+
+impl Operator {
+    fn compare(self, left: u32, right: u32) -> bool {
+        match self {
+            Self::BitMask => left & right != 0,
+            Self::LessThan => left < right,
+            Self::GreaterThan => left > right,
+            Self::NotEqual => left != right,
+            Self::Equal => left == right,
+            Self::BitTest => left & right == right,
+            Self::LessThanOrEqual => left <= right,
+            Self::GreaterThanOrEqual => left >= right,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RuleField {
+    type_: FieldType,
+    op: Operator,
+    value: u32,
+}
+
+/// An event that is checked against the rules.
+pub(super) struct MatchedEvent<'a> {
+    pub(super) task: &'a TaskInfo,
+    /// The system call, if the event is a system call.
+    pub(super) syscall: Option<&'a SyscallInfo>,
+    /// The record type, if the event is a record or a message sent from user space.
+    pub(super) msg_type: Option<u16>,
+}
+
+/// An audit rule.
+///
+/// A rule matches an event if all its fields match. The rules are checked in order, and the
+/// action of the first matched rule decides whether the event is audited.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct AuditRule {
+    list: FilterList,
+    action: RuleAction,
+    /// The system calls that the rule applies to, which is used only by [`FilterList::Exit`].
+    syscall_mask: [u32; AUDIT_BITMASK_SIZE],
+    fields: Vec<RuleField>,
+    key: Option<String>,
+}
+
+impl AuditRule {
+    /// Parses the rule from the bytes of `audit_rule_data`.
+    ///
+    /// This method also returns whether the rule should be added to the head of its list.
+    pub(crate) fn parse(bytes: &[u8]) -> Result<(Self, bool)> {
+        if bytes.len() < size_of::<CAuditRuleData>() {
+            return_errno_with_message!(Errno::EINVAL, "the rule is too short");
+        }
+        let (c_rule_bytes, mut buf) = bytes.split_at(size_of::<CAuditRuleData>());
+        let c_rule = CAuditRuleData::from_bytes(c_rule_bytes);
+        if buf.len() < c_rule.buflen as usize {
+            return_errno_with_message!(Errno::EINVAL, "the string buffer of the rule is too short");
+        }
+
+        let is_prepend = c_rule.flags & AUDIT_FILTER_PREPEND != 0;
+        let list = FilterList::try_from(c_rule.flags & !AUDIT_FILTER_PREPEND)
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the filter list is not supported"))?;
+        let action = RuleAction::try_from(c_rule.action)
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the rule action is invalid"))?;
+
+        let field_count = c_rule.field_count as usize;
+        if field_count > AUDIT_MAX_FIELDS {
+            return_errno_with_message!(Errno::EINVAL, "there are too many fields");
+        }
+
+        let mut fields = Vec::with_capacity(field_count);
+        let mut key = None;
+        for i in 0..field_count {
+            let type_ = FieldType::try_from(c_rule.fields[i])
+                .map_err(|_| Error::with_message(Errno::EINVAL, "the field is not supported"))?;
+            let op = Operator::try_from(c_rule.fieldflags[i])
+                .map_err(|_| Error::with_message(Errno::EINVAL, "the operator is invalid"))?;
+            let value = c_rule.values[i];
+
+            if !type_.is_allowed_in(list) {
+                return_errno_with_message!(Errno::EINVAL, "the field is not allowed in the list");
+            }
+
+            match type_ {
+                FieldType::Arch if !matches!(op, Operator::Equal | Operator::NotEqual) => {
+                    return_errno_with_message!(Errno::EINVAL, "the operator is invalid for arch");
+                }
+                FieldType::FilterKey => {
+                    let len = value as usize;
+                    if op != Operator::Equal || len > AUDIT_MAX_KEY_LEN || key.is_some() {
+                        return_errno_with_message!(Errno::EINVAL, "the filter key is invalid");
+                    }
+                    if len > buf.len() {
+                        return_errno_with_message!(Errno::EINVAL, "the filter key is too long");
+                    }
+                    let (key_bytes, rest) = buf.split_at(len);
+                    let key_str = core::str::from_utf8(key_bytes).map_err(|_| {
+                        Error::with_message(Errno::EINVAL, "the filter key is not a valid string")
+                    })?;
+                    key = Some(key_str.to_string());
+                    buf = rest;
+                }
+                _ => (),
+            }
+
+            fields.push(RuleField { type_, op, value });
+        }
+
+        let rule = Self {
+            list,
+            action,
+            syscall_mask: c_rule.mask,
+            fields,
+            key,
+        };
+        Ok((rule, is_prepend))
+    }
+
+    /// Returns the bytes of `audit_rule_data` that describe the rule.
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut c_rule = CAuditRuleData::new_zeroed();
+        c_rule.flags = self.list as u32;
+        c_rule.action = self.action as u32;
+        c_rule.field_count = self.fields.len() as u32;
+        c_rule.mask = self.syscall_mask;
+        for (i, field) in self.fields.iter().enumerate() {
+            c_rule.fields[i] = field.type_ as u32;
+            c_rule.values[i] = field.value;
+            c_rule.fieldflags[i] = field.op as u32;
+        }
+        let key = self.key.as_deref().unwrap_or_default();
+        c_rule.buflen = key.len() as u32;
+
+        let mut bytes = c_rule.as_bytes().to_vec();
+        bytes.extend_from_slice(key.as_bytes());
+        bytes
+    }
+
+    /// Returns the list of the rule.
+    pub(crate) fn list(&self) -> FilterList {
+        self.list
+    }
+
+    /// Returns the action of the rule.
+    pub(super) fn action(&self) -> RuleAction {
+        self.action
+    }
+
+    /// Returns the filter key of the rule, which is used to search the records.
+    pub(crate) fn key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+
+    /// Returns whether the rule matches the event.
+    pub(super) fn matches(&self, event: &MatchedEvent) -> bool {
+        if self.list == FilterList::Exit {
+            let Some(syscall) = event.syscall else {
+                return false;
+            };
+            let Some(word) = self.syscall_mask.get((syscall.number / 32) as usize) else {
+                return false;
+            };
+            if word & (1 << (syscall.number % 32)) == 0 {
+                return false;
+            }
+        }
+
+        self.fields.iter().all(|field| {
+            if field.type_ == FieldType::FilterKey {
+                return true;
+            }
+            field
+                .type_
+                .value_of(event)
+                .is_some_and(|value| field.op.compare(value, field.value))
+        })
+    }
+}
@@ -0,0 +1,118 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::fmt::{self, Display};
+
+use ostd::task::Task;
+
+use super::{AUDIT_UID_UNSET, record::UntrustedStr};
+use crate::{prelude::*, process::posix_thread::AsPosixThread};
+
+/// The information of the thread that causes an event.
+pub(super) struct TaskInfo {
+    pub(super) pid: u32,
+    pub(super) ppid: u32,
+    pub(super) uid: u32,
+    pub(super) euid: u32,
+    pub(super) suid: u32,
+    pub(super) fsuid: u32,
+    pub(super) gid: u32,
+    pub(super) egid: u32,
+    pub(super) sgid: u32,
+    pub(super) fsgid: u32,
+    comm: Vec<u8>,
+    exe: Option<String>,
+}
+
+impl TaskInfo {
+    /// Collects the information of the current thread.
+    ///
+    /// This method returns `None` if the current task is not a POSIX thread.
+    pub(super) fn current() -> Option<Self> {
+        let task = Task::current()?;
+        let posix_thread = task.as_posix_thread()?;
+        let process = posix_thread.process();
+        let credentials = posix_thread.credentials();
+
+        let comm = posix_thread.thread_name().lock().name().to_bytes().to_vec();
+        let exe = {
+            let vmar_guard = process.lock_vmar();
+            let thread_local = task.as_thread_local();
+            vmar_guard
+                .as_ref()
+                .zip(thread_local)
+                .map(|(vmar, thread_local)| {
+                    thread_local
+                        .borrow_fs()
+                        .resolver()
+                        .read()
+                        .make_abs_path(vmar.process_vm().executable_file())
+                        .into_string()
+                })
+        };
+
+        Some(Self {
+            pid: process.pid(),
+            ppid: process.parent().pid(),
+            uid: credentials.ruid().into(),
+            euid: credentials.euid().into(),
+            suid: credentials.suid().into(),
+            fsuid: credentials.fsuid().into(),
+            gid: credentials.rgid().into(),
+            egid: credentials.egid().into(),
+            sgid: credentials.sgid().into(),
+            fsgid: credentials.fsgid().into(),
+            comm,
+            exe,
+        })
+    }
+
+    /// Returns the object that formats the IDs of the thread.
+    pub(super) fn ids(&self) -> TaskIds<'_> {
+        TaskIds(self)
+    }
+
+    /// Returns the object that formats the executable of the thread.
+    pub(super) fn executable(&self) -> TaskExecutable<'_> {
+        TaskExecutable(self)
+    }
+}
+
+/// The IDs of a thread in an audit record, such as `pid=1 auid=0 uid=0 ...`.
+pub(super) struct TaskIds<'a>(&'a TaskInfo);
+
+impl Display for TaskIds<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let task = self.0;
+        write!(
+            f,
+            "ppid={} pid={} auid={} uid={} gid={} euid={} suid={} fsuid={} egid={} sgid={} \
+             fsgid={} tty=(none) ses={}",
+            task.ppid,
+            task.pid,
+            AUDIT_UID_UNSET,
+            task.uid,
+            task.gid,
+            task.euid,
+            task.suid,
+            task.fsuid,
+            task.egid,
+            task.sgid,
+            task.fsgid,
+            AUDIT_UID_UNSET
+        )
+    }
+}
+
+/// The executable of a thread in an audit record, such as `comm="ls" exe="/bin/ls"`.
+pub(super) struct TaskExecutable<'a>(&'a TaskInfo);
+
+impl Display for TaskExecutable<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let task = self.0;
+        write!(f, "comm={}", UntrustedStr(&task.comm))?;
+        match task.exe.as_ref() {
+            Some(exe) => write!(f, " exe={}", UntrustedStr(exe.as_bytes())),
+            None => write!(f, " exe=(null)"),
+        }
+    }
+}
@@ -0,0 +1,73 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::key::Key;
+use crate::prelude::*;
+
+/// The keyrings of a thread.
+#[derive(Clone)]
+pub(crate) struct KeyringContext {
+    /// The keyring that is specific to the thread.
+    pub(super) thread_keyring: Option<Arc<Key>>,
+    /// The keyring that is specific to the process.
+    ///
+    /// Like Linux, a process keyring that is created after other threads have been created is
+    /// not shared with the other threads.
+    pub(super) process_keyring: Option<Arc<Key>>,
+    /// The keyring that is inherited by new threads and processes, and which is kept on `execve`.
+    pub(super) session_keyring: Option<Arc<Key>>,
+    /// The default destination keyring for the keys that are requested by `request_key`.
+    pub(super) reqkey_default: ReqKeyDefault,
+}
+
+/// The default destination keyring for the keys that are requested by `request_key`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+#[repr(i32)]
+pub(crate) enum ReqKeyDefault {
+    Default = 0,
+    ThreadKeyring = 1,
+    ProcessKeyring = 2,
+    SessionKeyring = 3,
+    UserKeyring = 4,
+    UserSessionKeyring = 5,
+    GroupKeyring = 6,
+    RequestorKeyring = 7,
+}
+
+impl KeyringContext {
+    /// Creates the keyrings of the first thread, which are initially empty.
+    pub(crate) fn new() -> Self {
+        Self {
+            thread_keyring: None,
+            process_keyring: None,
+            session_keyring: None,
+            reqkey_default: ReqKeyDefault::Default,
+        }
+    }
+
+    /// Creates the keyrings of a new thread in the same process.
+    pub(crate) fn new_for_thread(&self) -> Self {
+        Self {
+            thread_keyring: None,
+            ..self.clone()
+        }
+    }
+
+    /// Creates the keyrings of the main thread in a new process.
+    pub(crate) fn new_for_process(&self) -> Self {
+        Self {
+            thread_keyring: None,
+            process_keyring: None,
+            ..self.clone()
+        }
+    }
+
+    pub(crate) fn reqkey_default(&self) -> ReqKeyDefault {
+        self.reqkey_default
+    }
+
+    /// Drops the thread keyring and the process keyring on `execve`.
+    pub(crate) fn reset_on_exec(&mut self) {
+        self.thread_keyring = None;
+        self.process_keyring = None;
+    }
+}
@@ -0,0 +1,382 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::collections::btree_map::BTreeMap;
+use core::{
+    fmt::{self, Display},
+    sync::atomic::{AtomicBool, AtomicI32, Ordering},
+    time::Duration,
+};
+
+use crate::{
+    prelude::*,
+    process::credentials::{Gid, Uid},
+    time::clocks::RealTimeCoarseClock,
+};
+
+/// The serial number of a key, which is always positive.
+pub(crate) type KeySerial = i32;
+
+/// The keys that are alive, indexed by their serial numbers.
+static KEYS: Mutex<BTreeMap<KeySerial, Weak<Key>>> = Mutex::new(BTreeMap::new());
+
+/// The serial number that is tried first for the next key.
+static NEXT_SERIAL: AtomicI32 = AtomicI32::new(FIRST_SERIAL);
+
+/// The smallest serial number.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/security/keys/key.c#L153>.
+const FIRST_SERIAL: KeySerial = 3;
+
+/// The maximum length of the payload of `user` and `logon` keys.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/security/keys/user_defined.c#L63>.
+const MAX_USER_PAYLOAD_LEN: usize = 32767;
+
+/// A key, which holds a piece of data (e.g., a secret) or links to other keys.
+///
+/// Keys are destroyed when they are no longer referenced, e.g., after they are unlinked from all
+/// keyrings.
+pub(crate) struct Key {
+    serial: KeySerial,
+    type_: KeyType,
+    description: String,
+    attrs: SpinLock<KeyAttrs>,
+    payload: Mutex<KeyPayload>,
+    is_revoked: AtomicBool,
+    is_invalidated: AtomicBool,
+}
+
+/// The attributes of a key that can be changed by `keyctl`.
+#[derive(Clone, Copy)]
+pub(crate) struct KeyAttrs {
+    pub(crate) uid: Uid,
+    pub(crate) gid: Gid,
+    pub(crate) perm: KeyPermMask,
+    /// The time (measured by the real-time clock) after which the key expires.
+    pub(crate) expiry: Option<Duration>,
+}
+
+/// The payload of a key.
+pub(super) enum KeyPayload {
+    /// The data of a `user` or `logon` key.
+    Data(Vec<u8>),
+    /// The keys that are linked to a keyring.
+    Keyring(Vec<Arc<Key>>),
+}
+
+/// The type of a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum KeyType {
+    /// A keyring, which contains links to other keys.
+    Keyring,
+    /// A key that holds arbitrary data, which can be read by user space.
+    User,
+    /// A key that holds arbitrary data, which cannot be read by user space.
+    ///
+    /// The key is intended to be used by kernel services, e.g., to hold the passwords of network
+    /// file systems.
+    Logon,
+}
+
+impl KeyType {
+    /// Looks up the key type by its name.
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "keyring" => Some(Self::Keyring),
+            "user" => Some(Self::User),
+            "logon" => Some(Self::Logon),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Keyring => "keyring",
+            Self::User => "user",
+            Self::Logon => "logon",
+        }
+    }
+
+    /// Checks whether the description and the payload are valid for keys of this type.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.18/source/security/keys/user_defined.c>.
+    pub(crate) fn check(self, description: &str, payload: &[u8]) -> Result<()> {
+        match self {
+            Self::Keyring if !payload.is_empty() => {
+                return_errno_with_message!(Errno::EINVAL, "keyrings cannot have payloads");
+            }
+            Self::Keyring => (),
+            Self::User | Self::Logon => self.check_data(payload)?,
+        }
+
+        if self == Self::Logon && description.find(':').is_none_or(|pos| pos == 0) {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "the description of a logon key does not start with a service name"
+            );
+        }
+
+        Ok(())
+    }
+
+    fn check_data(self, data: &[u8]) -> Result<()> {
+        if data.is_empty() || data.len() > MAX_USER_PAYLOAD_LEN {
+            return_errno_with_message!(Errno::EINVAL, "the payload length is invalid");
+        }
+        Ok(())
+    }
+}
+
+impl Display for KeyType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+bitflags! {
+    /// The permissions that are granted on a key.
+    pub(crate) struct KeyPerm: u32 {
+        /// Allows viewing the attributes of the key.
+        const VIEW    = 0x01;
+        /// Allows reading the payload of the key, or the links of the keyring.
+        const READ    = 0x02;
+        /// Allows updating the payload of the key, or adding and removing links of the keyring.
+        const WRITE   = 0x04;
+        /// Allows finding the key, or searching the keyring.
+        const SEARCH  = 0x08;
+        /// Allows linking the key to keyrings.
+        const LINK    = 0x10;
+        /// Allows changing the attributes of the key.
+        const SETATTR = 0x20;
+    }
+}
+
+/// The permissions of a key for possessors, the owner, the group, and others.
+///
+/// Each class takes eight bits, from possessors in the most significant byte to others in the
+/// least significant byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct KeyPermMask(u32);
+
+impl KeyPermMask {
+    pub(crate) const fn new(
+        possessor: KeyPerm,
+        user: KeyPerm,
+        group: KeyPerm,
+        other: KeyPerm,
+    ) -> Self {
+        Self(possessor.bits() << 24 | user.bits() << 16 | group.bits() << 8 | other.bits())
+    }
+
+    /// Converts the raw value to permissions, failing if there are unknown bits.
+    pub(crate) fn from_raw(raw: u32) -> Result<Self> {
+        let all = KeyPerm::all();
+        if raw & !Self::new(all, all, all, all).0 != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the key permissions are invalid");
+        }
+        Ok(Self(raw))
+    }
+
+    pub(crate) fn as_raw(self) -> u32 {
+        self.0
+    }
+
+    pub(crate) fn possessor(self) -> KeyPerm {
+        KeyPerm::from_bits_truncate(self.0 >> 24)
+    }
+
+    pub(crate) fn user(self) -> KeyPerm {
+        KeyPerm::from_bits_truncate(self.0 >> 16)
+    }
+
+    pub(crate) fn group(self) -> KeyPerm {
+        KeyPerm::from_bits_truncate(self.0 >> 8)
+    }
+
+    pub(crate) fn other(self) -> KeyPerm {
+        KeyPerm::from_bits_truncate(self.0)
+    }
+}
+
+impl Key {
+    /// Creates a new key and allocates a serial number for it.
+    pub(super) fn new(
+        type_: KeyType,
+        description: String,
+        uid: Uid,
+        gid: Gid,
+        perm: KeyPermMask,
+        data: Vec<u8>,
+    ) -> Arc<Self> {
+        let payload = match type_ {
+            KeyType::Keyring => KeyPayload::Keyring(Vec::new()),
+            KeyType::User | KeyType::Logon => KeyPayload::Data(data),
+        };
+
+        let mut keys = KEYS.lock();
+        let serial = loop {
+            let serial = NEXT_SERIAL.fetch_add(1, Ordering::Relaxed);
+            if serial < FIRST_SERIAL {
+                // The serial numbers have wrapped around.
+                NEXT_SERIAL.store(FIRST_SERIAL, Ordering::Relaxed);
+                continue;
+            }
+            if !keys.contains_key(&serial) {
+                break serial;
+            }
+        };
+
+        let key = Arc::new(Self {
+            serial,
+            type_,
+            description,
+            attrs: SpinLock::new(KeyAttrs {
+                uid,
+                gid,
+                perm,
+                expiry: None,
+            }),
+            payload: Mutex::new(payload),
+            is_revoked: AtomicBool::new(false),
+            is_invalidated: AtomicBool::new(false),
+        });
+        keys.insert(serial, Arc::downgrade(&key));
+
+        key
+    }
+
+    /// Creates a new keyring.
+    pub(super) fn new_keyring(
+        description: String,
+        uid: Uid,
+        gid: Gid,
+        perm: KeyPermMask,
+    ) -> Arc<Self> {
+        Self::new(KeyType::Keyring, description, uid, gid, perm, Vec::new())
+    }
+
+    /// Finds the key by its serial number.
+    ///
+    /// Keys that have been invalidated cannot be found.
+    pub(super) fn find(serial: KeySerial) -> Option<Arc<Self>> {
+        // Dropping the key may remove it from `KEYS`, so the lock must be released first.
+        let key = KEYS.lock().get(&serial).and_then(Weak::upgrade)?;
+        (!key.is_invalidated()).then_some(key)
+    }
+
+    /// Returns the keys that are alive.
+    pub(super) fn all() -> Vec<Arc<Self>> {
+        KEYS.lock().values().filter_map(Weak::upgrade).collect()
+    }
+
+    pub(crate) fn serial(&self) -> KeySerial {
+        self.serial
+    }
+
+    pub(crate) fn type_(&self) -> KeyType {
+        self.type_
+    }
+
+    pub(crate) fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub(crate) fn attrs(&self) -> KeyAttrs {
+        *self.attrs.lock()
+    }
+
+    pub(crate) fn set_owner(&self, uid: Option<Uid>, gid: Option<Gid>) {
+        let mut attrs = self.attrs.lock();
+        if let Some(uid) = uid {
+            attrs.uid = uid;
+        }
+        if let Some(gid) = gid {
+            attrs.gid = gid;
+        }
+    }
+
+    pub(crate) fn set_perm(&self, perm: KeyPermMask) {
+        self.attrs.lock().perm = perm;
+    }
+
+    /// Sets the key to expire after the timeout, or never expire if the timeout is `None`.
+    pub(crate) fn set_timeout(&self, timeout: Option<Duration>) {
+        let now = RealTimeCoarseClock::get().read_time();
+        self.attrs.lock().expiry = timeout.map(|timeout| now + timeout);
+    }
+
+    pub(super) fn payload(&self) -> MutexGuard<'_, KeyPayload> {
+        self.payload.lock()
+    }
+
+    /// Reads the payload of the key.
+    ///
+    /// The payload of a keyring is read as the serial numbers of the linked keys.
+    pub(crate) fn read(&self) -> Result<Vec<u8>> {
+        if self.type_ == KeyType::Logon {
+            return_errno_with_message!(Errno::EOPNOTSUPP, "logon keys cannot be read");
+        }
+
+        match &*self.payload.lock() {
+            KeyPayload::Data(data) => Ok(data.clone()),
+            KeyPayload::Keyring(links) => Ok(links
+                .iter()
+                .filter(|key| !key.is_invalidated())
+                .flat_map(|key| key.serial.to_ne_bytes())
+                .collect()),
+        }
+    }
+
+    /// Replaces the data of the key.
+    pub(crate) fn update(&self, data: Vec<u8>) -> Result<()> {
+        let mut payload = self.payload.lock();
+        let KeyPayload::Data(old_data) = &mut *payload else {
+            return_errno_with_message!(Errno::EOPNOTSUPP, "keyrings cannot be updated");
+        };
+
+        self.type_.check_data(&data)?;
+        *old_data = data;
+
+        Ok(())
+    }
+
+    pub(crate) fn revoke(&self) {
+        self.is_revoked.store(true, Ordering::Relaxed);
+    }
+
+    /// Invalidates the key, so the key can no longer be found and is unlinked from keyrings.
+    pub(crate) fn invalidate(&self) {
+        self.is_invalidated.store(true, Ordering::Relaxed);
+    }
+
+    pub(crate) fn is_invalidated(&self) -> bool {
+        self.is_invalidated.load(Ordering::Relaxed)
+    }
+
+    fn is_expired(&self) -> bool {
+        let Some(expiry) = self.attrs.lock().expiry else {
+            return false;
+        };
+        RealTimeCoarseClock::get().read_time() >= expiry
+    }
+
+    /// Checks whether the key can still be used.
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.is_invalidated() {
+            return_errno_with_message!(Errno::ENOKEY, "the key has been invalidated");
+        }
+        if self.is_revoked.load(Ordering::Relaxed) {
+            return_errno_with_message!(Errno::EKEYREVOKED, "the key has been revoked");
+        }
+        if self.is_expired() {
+            return_errno_with_message!(Errno::EKEYEXPIRED, "the key has expired");
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Key {
+    fn drop(&mut self) {
+        KEYS.lock().remove(&self.serial);
+    }
+}
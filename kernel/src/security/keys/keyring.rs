@@ -0,0 +1,167 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::key::{Key, KeyPayload, KeyType};
+use crate::prelude::*;
+
+/// The maximum depth of nested keyrings.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/security/keys/internal.h#L139>.
+const MAX_DEPTH: usize = 6;
+
+/// The lock that serializes linking, so that concurrent links cannot create cycles.
+static LINK_LOCK: Mutex<()> = Mutex::new(());
+
+impl Key {
+    /// Returns the keys that are linked to the keyring.
+    pub(crate) fn links(&self) -> Result<Vec<Arc<Key>>> {
+        match &mut *self.payload() {
+            KeyPayload::Keyring(links) => {
+                links.retain(|key| !key.is_invalidated());
+                Ok(links.clone())
+            }
+            KeyPayload::Data(_) => {
+                return_errno_with_message!(Errno::ENOTDIR, "the key is not a keyring")
+            }
+        }
+    }
+
+    /// Finds the key of the type and the description that is linked to the keyring.
+    pub(crate) fn find_link(&self, type_: KeyType, description: &str) -> Result<Option<Arc<Key>>> {
+        Ok(self
+            .links()?
+            .into_iter()
+            .find(|key| key.type_() == type_ && key.description() == description))
+    }
+
+    /// Links the key to the keyring.
+    ///
+    /// If the keyring has a link to another key of the same type and the same description, the
+    /// link is replaced.
+    pub(crate) fn link(self: &Arc<Self>, key: Arc<Key>) -> Result<()> {
+        if self.type_() != KeyType::Keyring {
+            return_errno_with_message!(Errno::ENOTDIR, "the key is not a keyring");
+        }
+
+        let _guard = LINK_LOCK.lock();
+
+        if key.type_() == KeyType::Keyring {
+            if Arc::ptr_eq(self, &key) {
+                return_errno_with_message!(Errno::EDEADLK, "a keyring cannot be linked to itself");
+            }
+            check_cycle(&key, self, 1)?;
+        }
+
+        let mut payload = self.payload();
+        let KeyPayload::Keyring(links) = &mut *payload else {
+            unreachable!("the payload of a keyring is not a list of links")
+        };
+        links.retain(|link| {
+            !link.is_invalidated()
+                && (link.type_() != key.type_() || link.description() != key.description())
+        });
+        links.push(key);
+
+        Ok(())
+    }
+
+    /// Unlinks the key from the keyring.
+    pub(crate) fn unlink(&self, key: &Arc<Key>) -> Result<()> {
+        let mut payload = self.payload();
+        let KeyPayload::Keyring(links) = &mut *payload else {
+            return_errno_with_message!(Errno::ENOTDIR, "the key is not a keyring");
+        };
+
+        let Some(pos) = links.iter().position(|link| Arc::ptr_eq(link, key)) else {
+            return_errno_with_message!(Errno::ENOENT, "the key is not linked to the keyring");
+        };
+        links.remove(pos);
+
+        Ok(())
+    }
+
+    /// Unlinks all the keys from the keyring.
+    pub(crate) fn clear(&self) -> Result<()> {
+        let mut payload = self.payload();
+        let KeyPayload::Keyring(links) = &mut *payload else {
+            return_errno_with_message!(Errno::ENOTDIR, "the key is not a keyring");
+        };
+        links.clear();
+
+        Ok(())
+    }
+
+    /// Searches the keyring and the keyrings nested in it for a key.
+    ///
+    /// The keys that are directly linked to a keyring are matched before the nested keyrings are
+    /// searched. A nested keyring is searched only if `is_searchable` returns true for it.
+    ///
+    /// Keys that are revoked or expired are skipped. If only such keys are matched, the search
+    /// fails with the error that describes the status of the first matched key.
+    pub(crate) fn search(
+        &self,
+        is_match: &mut dyn FnMut(&Arc<Key>) -> bool,
+        is_searchable: &mut dyn FnMut(&Arc<Key>) -> bool,
+    ) -> Result<Arc<Key>> {
+        let mut error = None;
+        if let Some(key) = search_nested(&self.links()?, is_match, is_searchable, 1, &mut error) {
+            return Ok(key);
+        }
+        Err(error.unwrap_or_else(|| Error::with_message(Errno::ENOKEY, "the key is not found")))
+    }
+}
+
+fn search_nested(
+    links: &[Arc<Key>],
+    is_match: &mut dyn FnMut(&Arc<Key>) -> bool,
+    is_searchable: &mut dyn FnMut(&Arc<Key>) -> bool,
+    depth: usize,
+    error: &mut Option<Error>,
+) -> Option<Arc<Key>> {
+    for key in links.iter().filter(|key| is_match(key)) {
+        match key.validate() {
+            Ok(()) => return Some(key.clone()),
+            Err(err) => {
+                error.get_or_insert(err);
+            }
+        }
+    }
+    if depth >= MAX_DEPTH {
+        return None;
+    }
+
+    for keyring in links {
+        if keyring.type_() != KeyType::Keyring
+            || keyring.validate().is_err()
+            || !is_searchable(keyring)
+        {
+            continue;
+        }
+        let Ok(nested_links) = keyring.links() else {
+            continue;
+        };
+        if let Some(key) = search_nested(&nested_links, is_match, is_searchable, depth + 1, error) {
+            return Some(key);
+        }
+    }
+
+    None
+}
+
+/// Checks that linking `keyring` to `target` does not create a cycle or a deep nesting.
+fn check_cycle(keyring: &Arc<Key>, target: &Arc<Key>, depth: usize) -> Result<()> {
+    if depth >= MAX_DEPTH {
+        return_errno_with_message!(Errno::ELOOP, "the keyrings are nested too deeply");
+    }
+
+    for nested in keyring.links()? {
+        if nested.type_() != KeyType::Keyring {
+            continue;
+        }
+        if Arc::ptr_eq(&nested, target) {
+            return_errno_with_message!(Errno::EDEADLK, "the link would create a cycle");
+        }
+        check_cycle(&nested, target, depth + 1)?;
+    }
+
+    Ok(())
+}
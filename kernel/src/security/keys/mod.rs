@@ -0,0 +1,495 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The kernel key retention service.
+//!
+//! Keys hold small pieces of data (e.g., secrets) in the kernel, and keyrings link to other keys.
+//! Each thread has a thread keyring, a process keyring, and a session keyring, and each user has
+//! a user keyring and a user session keyring. These keyrings are created on demand.
+//!
+//! A key is possessed by a thread if the key can be found by searching the keyrings of the
+//! thread. In addition to the permissions for the owner, the group, and others, a key grants
+//! permissions to its possessors.
+//!
+//! Reference: <https://docs.kernel.org/security/keys/core.html>.
+
+mod context;
+mod key;
+mod keyring;
+
+use alloc::format;
+
+pub(crate) use self::{
+    context::{KeyringContext, ReqKeyDefault},
+    key::{Key, KeyPerm, KeyPermMask, KeySerial, KeyType},
+};
+use crate::{
+    prelude::*,
+    process::{
+        credentials::{Gid, Uid},
+        posix_thread::PosixThread,
+    },
+};
+
+/// The maximum length of a key description, including the final nul byte.
+pub(crate) const KEY_MAX_DESC_SIZE: usize = 4096;
+
+/// The user keyrings and the user session keyrings, indexed by the UIDs.
+static USER_KEYRINGS: Mutex<BTreeMap<u32, UserKeyrings>> = Mutex::new(BTreeMap::new());
+
+#[derive(Clone)]
+struct UserKeyrings {
+    user_keyring: Arc<Key>,
+    user_session_keyring: Arc<Key>,
+}
+
+/// The special serial numbers that refer to the keyrings of the current thread.
+#[derive(Debug, Clone, Copy, TryFromInt)]
+#[repr(i32)]
+enum SpecialKeyring {
+    Thread = -1,
+    Process = -2,
+    Session = -3,
+    User = -4,
+    UserSession = -5,
+    Group = -6,
+    ReqKeyAuth = -7,
+    Requestor = -8,
+}
+
+/// A reference to a key, which records whether the key is possessed by the current thread.
+pub(crate) struct KeyRef {
+    key: Arc<Key>,
+    is_possessed: bool,
+}
+
+impl KeyRef {
+    pub(crate) fn key(&self) -> &Arc<Key> {
+        &self.key
+    }
+
+    pub(crate) fn is_possessed(&self) -> bool {
+        self.is_possessed
+    }
+
+    /// Checks whether the thread is granted the permissions on the key.
+    pub(crate) fn check_perm(&self, posix_thread: &PosixThread, perm: KeyPerm) -> Result<()> {
+        if !granted_perm(&self.key, self.is_possessed, posix_thread).contains(perm) {
+            return_errno_with_message!(Errno::EACCES, "the key permissions are not granted");
+        }
+        Ok(())
+    }
+}
+
+/// Looks up the key by its serial number, which may also be a special serial number that refers
+/// to a keyring of the thread.
+///
+/// If `create` is true, the special keyring is created if it does not exist. The status and the
+/// permissions of the key are not checked.
+pub(crate) fn lookup_key(
+    posix_thread: &PosixThread,
+    serial: KeySerial,
+    create: bool,
+) -> Result<KeyRef> {
+    if serial > 0 {
+        let Some(key) = Key::find(serial) else {
+            return_errno_with_message!(Errno::ENOKEY, "the key does not exist");
+        };
+        let is_possessed = is_possessed(posix_thread, &key);
+        return Ok(KeyRef { key, is_possessed });
+    }
+
+    let Ok(special) = SpecialKeyring::try_from(serial) else {
+        return_errno_with_message!(Errno::EINVAL, "the key serial number is invalid");
+    };
+    let key = match special {
+        SpecialKeyring::Thread => thread_keyring(posix_thread, create)?,
+        SpecialKeyring::Process => process_keyring(posix_thread, create)?,
+        SpecialKeyring::Session => session_keyring(posix_thread, create)?,
+        SpecialKeyring::User => user_keyrings(posix_thread).user_keyring,
+        SpecialKeyring::UserSession => user_keyrings(posix_thread).user_session_keyring,
+        SpecialKeyring::ReqKeyAuth => {
+            return_errno_with_message!(Errno::ENOKEY, "the thread is not constructing a key");
+        }
+        SpecialKeyring::Group | SpecialKeyring::Requestor => {
+            return_errno_with_message!(Errno::EINVAL, "the special keyring is not supported");
+        }
+    };
+
+    Ok(KeyRef {
+        key,
+        is_possessed: true,
+    })
+}
+
+/// Looks up the key, and checks that the key is valid and that the permissions are granted.
+pub(crate) fn lookup_key_with_perm(
+    posix_thread: &PosixThread,
+    serial: KeySerial,
+    create: bool,
+    perm: KeyPerm,
+) -> Result<KeyRef> {
+    let key_ref = lookup_key(posix_thread, serial, create)?;
+    key_ref.key.validate()?;
+    key_ref.check_perm(posix_thread, perm)?;
+    Ok(key_ref)
+}
+
+/// Adds a key to the keyring.
+///
+/// If the keyring already has a key of the same type and the same description, the key is
+/// updated instead, unless it is a keyring, which is replaced by a new keyring.
+pub(crate) fn add_key(
+    posix_thread: &PosixThread,
+    type_: KeyType,
+    description: String,
+    payload: Vec<u8>,
+    keyring: &KeyRef,
+) -> Result<Arc<Key>> {
+    if keyring.key.type_() != KeyType::Keyring {
+        return_errno_with_message!(Errno::ENOTDIR, "the key is not a keyring");
+    }
+    type_.check(&description, &payload)?;
+
+    let old_key = if type_ != KeyType::Keyring {
+        keyring.key.find_link(type_, &description)?
+    } else {
+        None
+    };
+    if let Some(key) = old_key {
+        let key_ref = KeyRef {
+            key,
+            is_possessed: keyring.is_possessed,
+        };
+        key_ref.check_perm(posix_thread, KeyPerm::WRITE)?;
+        key_ref.key.update(payload)?;
+        return Ok(key_ref.key);
+    }
+
+    let mut possessor_perm = KeyPerm::all();
+    if type_ == KeyType::Logon {
+        possessor_perm.remove(KeyPerm::READ);
+    }
+    let perm = KeyPermMask::new(
+        possessor_perm,
+        KeyPerm::VIEW,
+        KeyPerm::empty(),
+        KeyPerm::empty(),
+    );
+    let (uid, gid) = owner(posix_thread);
+    let key = Key::new(type_, description, uid, gid, perm, payload);
+    keyring.key.link(key.clone())?;
+
+    Ok(key)
+}
+
+/// Searches the keyrings of the thread for the key of the type and the description.
+///
+/// Unlike Linux, the key is not constructed by calling out to user space if it cannot be found.
+pub(crate) fn request_key(
+    posix_thread: &PosixThread,
+    type_: KeyType,
+    description: &str,
+) -> Result<KeyRef> {
+    let key = search_thread_keyrings(posix_thread, &mut |key| {
+        key.type_() == type_ && key.description() == description
+    })?;
+
+    Ok(KeyRef {
+        key,
+        is_possessed: true,
+    })
+}
+
+/// Searches the keyring for the key of the type and the description.
+pub(crate) fn search_keyring(
+    posix_thread: &PosixThread,
+    keyring: &KeyRef,
+    type_: KeyType,
+    description: &str,
+) -> Result<KeyRef> {
+    let is_possessed = keyring.is_possessed;
+    let key = keyring.key.search(
+        &mut |key| {
+            key.type_() == type_
+                && key.description() == description
+                && granted_perm(key, is_possessed, posix_thread).contains(KeyPerm::SEARCH)
+        },
+        &mut |keyring| granted_perm(keyring, is_possessed, posix_thread).contains(KeyPerm::SEARCH),
+    )?;
+
+    Ok(KeyRef { key, is_possessed })
+}
+
+/// Joins a new anonymous session keyring, or the session keyring of the name.
+///
+/// Returns the serial number of the session keyring, or zero if the thread has already joined the
+/// session keyring of the name.
+pub(crate) fn join_session_keyring(
+    posix_thread: &PosixThread,
+    name: Option<String>,
+) -> Result<KeySerial> {
+    let Some(name) = name else {
+        let keyring = new_keyring(posix_thread, "_ses".to_string(), SESSION_KEYRING_PERM);
+        let serial = keyring.serial();
+        posix_thread.keyrings().lock().session_keyring = Some(keyring);
+        return Ok(serial);
+    };
+
+    // Reference: <https://elixir.bootlin.com/linux/v6.18/source/security/keys/keyring.c#L1156>
+    let named_keyring = Key::all().into_iter().find(|key| {
+        key.type_() == KeyType::Keyring
+            && key.description() == name
+            && !name.starts_with('.')
+            && key.validate().is_ok()
+            && granted_perm(key, false, posix_thread).contains(KeyPerm::SEARCH)
+    });
+
+    let keyring = match named_keyring {
+        Some(keyring) => {
+            let keyrings = posix_thread.keyrings().lock();
+            if keyrings
+                .session_keyring
+                .as_ref()
+                .is_some_and(|session_keyring| Arc::ptr_eq(session_keyring, &keyring))
+            {
+                return Ok(0);
+            }
+            keyring
+        }
+        None => new_keyring(posix_thread, name, NAMED_SESSION_KEYRING_PERM),
+    };
+
+    let serial = keyring.serial();
+    posix_thread.keyrings().lock().session_keyring = Some(keyring);
+    Ok(serial)
+}
+
+/// Replaces the session keyring of the thread with the keyring.
+pub(crate) fn set_session_keyring(posix_thread: &PosixThread, keyring: Arc<Key>) {
+    posix_thread.keyrings().lock().session_keyring = Some(keyring);
+}
+
+/// Sets the default destination keyring for the keys that are requested by `request_key`.
+///
+/// Returns the old default destination keyring.
+pub(crate) fn set_reqkey_default(
+    posix_thread: &PosixThread,
+    reqkey_default: ReqKeyDefault,
+) -> Result<ReqKeyDefault> {
+    match reqkey_default {
+        ReqKeyDefault::ThreadKeyring => {
+            thread_keyring(posix_thread, true)?;
+        }
+        ReqKeyDefault::ProcessKeyring => {
+            process_keyring(posix_thread, true)?;
+        }
+        ReqKeyDefault::GroupKeyring => {
+            return_errno_with_message!(Errno::EINVAL, "group keyrings are not supported");
+        }
+        ReqKeyDefault::Default
+        | ReqKeyDefault::SessionKeyring
+        | ReqKeyDefault::UserKeyring
+        | ReqKeyDefault::UserSessionKeyring
+        | ReqKeyDefault::RequestorKeyring => (),
+    }
+
+    let mut keyrings = posix_thread.keyrings().lock();
+    let old_reqkey_default = keyrings.reqkey_default;
+    keyrings.reqkey_default = reqkey_default;
+    Ok(old_reqkey_default)
+}
+
+/// The permissions of the thread keyrings and the process keyrings.
+const THREAD_KEYRING_PERM: KeyPermMask = KeyPermMask::new(
+    KeyPerm::all(),
+    KeyPerm::VIEW,
+    KeyPerm::empty(),
+    KeyPerm::empty(),
+);
+
+/// The permissions of the anonymous session keyrings.
+const SESSION_KEYRING_PERM: KeyPermMask = KeyPermMask::new(
+    KeyPerm::all(),
+    KeyPerm::VIEW.union(KeyPerm::READ),
+    KeyPerm::empty(),
+    KeyPerm::empty(),
+);
+
+/// The permissions of the named session keyrings.
+const NAMED_SESSION_KEYRING_PERM: KeyPermMask = KeyPermMask::new(
+    KeyPerm::all(),
+    KeyPerm::VIEW.union(KeyPerm::READ).union(KeyPerm::LINK),
+    KeyPerm::empty(),
+    KeyPerm::empty(),
+);
+
+/// The permissions of the user keyrings and the user session keyrings.
+const USER_KEYRING_PERM: KeyPermMask = KeyPermMask::new(
+    KeyPerm::all().difference(KeyPerm::SETATTR),
+    KeyPerm::all(),
+    KeyPerm::empty(),
+    KeyPerm::empty(),
+);
+
+fn thread_keyring(posix_thread: &PosixThread, create: bool) -> Result<Arc<Key>> {
+    if let Some(keyring) = posix_thread.keyrings().lock().thread_keyring.clone() {
+        return Ok(keyring);
+    }
+    if !create {
+        return_errno_with_message!(Errno::ENOKEY, "the thread keyring does not exist");
+    }
+
+    let keyring = new_keyring(posix_thread, "_tid".to_string(), THREAD_KEYRING_PERM);
+    posix_thread.keyrings().lock().thread_keyring = Some(keyring.clone());
+    Ok(keyring)
+}
+
+fn process_keyring(posix_thread: &PosixThread, create: bool) -> Result<Arc<Key>> {
+    if let Some(keyring) = posix_thread.keyrings().lock().process_keyring.clone() {
+        return Ok(keyring);
+    }
+    if !create {
+        return_errno_with_message!(Errno::ENOKEY, "the process keyring does not exist");
+    }
+
+    let keyring = new_keyring(posix_thread, "_pid".to_string(), THREAD_KEYRING_PERM);
+    posix_thread.keyrings().lock().process_keyring = Some(keyring.clone());
+    Ok(keyring)
+}
+
+fn session_keyring(posix_thread: &PosixThread, create: bool) -> Result<Arc<Key>> {
+    if let Some(keyring) = posix_thread.keyrings().lock().session_keyring.clone() {
+        return Ok(keyring);
+    }
+
+    // Like Linux, a thread without a session keyring joins a new anonymous session keyring if it
+    // is asked to create one, or joins the user session keyring otherwise.
+    //
+    // Reference: <https://elixir.bootlin.com/linux/v6.18/source/security/keys/process_keys.c#L680>
+    let keyring = if create {
+        new_keyring(posix_thread, "_ses".to_string(), SESSION_KEYRING_PERM)
+    } else {
+        user_keyrings(posix_thread).user_session_keyring
+    };
+    posix_thread.keyrings().lock().session_keyring = Some(keyring.clone());
+    Ok(keyring)
+}
+
+/// Returns the user keyring and the user session keyring of the real UID of the thread.
+fn user_keyrings(posix_thread: &PosixThread) -> UserKeyrings {
+    let uid = posix_thread.credentials().ruid();
+
+    let mut user_keyrings = USER_KEYRINGS.lock();
+    user_keyrings
+        .entry(uid.into())
+        .or_insert_with(|| {
+            let user_keyring = Key::new_keyring(
+                format!("_uid.{}", u32::from(uid)),
+                uid,
+                Gid::INVALID,
+                USER_KEYRING_PERM,
+            );
+            let user_session_keyring = Key::new_keyring(
+                format!("_uid_ses.{}", u32::from(uid)),
+                uid,
+                Gid::INVALID,
+                USER_KEYRING_PERM,
+            );
+            // The user keyring is possessed by the threads that use the user session keyring.
+            user_session_keyring.link(user_keyring.clone()).unwrap();
+
+            UserKeyrings {
+                user_keyring,
+                user_session_keyring,
+            }
+        })
+        .clone()
+}
+
+fn new_keyring(posix_thread: &PosixThread, description: String, perm: KeyPermMask) -> Arc<Key> {
+    let (uid, gid) = owner(posix_thread);
+    Key::new_keyring(description, uid, gid, perm)
+}
+
+/// Returns the owner of the keys that are created by the thread.
+fn owner(posix_thread: &PosixThread) -> (Uid, Gid) {
+    let credentials = posix_thread.credentials();
+    (credentials.fsuid(), credentials.fsgid())
+}
+
+/// Returns the permissions that the key grants to the thread.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/security/keys/permission.c#L27>.
+fn granted_perm(key: &Key, is_possessed: bool, posix_thread: &PosixThread) -> KeyPerm {
+    let attrs = key.attrs();
+    let credentials = posix_thread.credentials();
+
+    let mut perm = if attrs.uid == credentials.fsuid() {
+        attrs.perm.user()
+    } else if attrs.gid != Gid::INVALID
+        && (attrs.gid == credentials.fsgid() || credentials.groups().contains(&attrs.gid))
+    {
+        attrs.perm.group()
+    } else {
+        attrs.perm.other()
+    };
+    if is_possessed {
+        perm |= attrs.perm.possessor();
+    }
+
+    perm
+}
+
+/// Returns whether the key is possessed by the thread.
+fn is_possessed(posix_thread: &PosixThread, key: &Arc<Key>) -> bool {
+    search_thread_keyrings(posix_thread, &mut |found| Arc::ptr_eq(found, key)).is_ok()
+}
+
+/// Searches the thread keyring, the process keyring, and the session keyring of the thread.
+///
+/// If the thread has no session keyring, the user session keyring is searched instead. The
+/// keyrings themselves are also matched.
+fn search_thread_keyrings(
+    posix_thread: &PosixThread,
+    is_match: &mut dyn FnMut(&Arc<Key>) -> bool,
+) -> Result<Arc<Key>> {
+    let (thread_keyring, process_keyring, session_keyring) = {
+        let keyrings = posix_thread.keyrings().lock();
+        (
+            keyrings.thread_keyring.clone(),
+            keyrings.process_keyring.clone(),
+            keyrings.session_keyring.clone(),
+        )
+    };
+    let session_keyring =
+        session_keyring.unwrap_or_else(|| user_keyrings(posix_thread).user_session_keyring);
+
+    let mut error = None;
+    for keyring in [thread_keyring, process_keyring, Some(session_keyring)]
+        .into_iter()
+        .flatten()
+    {
+        if is_match(&keyring) {
+            return Ok(keyring);
+        }
+        if !granted_perm(&keyring, true, posix_thread).contains(KeyPerm::SEARCH) {
+            continue;
+        }
+
+        let result = keyring.search(
+            &mut |key| {
+                is_match(key) && granted_perm(key, true, posix_thread).contains(KeyPerm::SEARCH)
+            },
+            &mut |keyring| granted_perm(keyring, true, posix_thread).contains(KeyPerm::SEARCH),
+        );
+        match result {
+            Ok(key) => return Ok(key),
+            Err(err) if err.error() == Errno::ENOKEY => (),
+            Err(err) => {
+                error.get_or_insert(err);
+            }
+        }
+    }
+
+    Err(error.unwrap_or_else(|| Error::with_message(Errno::ENOKEY, "the key is not found")))
+}
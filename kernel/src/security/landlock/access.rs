@@ -0,0 +1,72 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{fs::file::InodeType, prelude::*};
+
+bitflags! {
+    /// The access rights to files, which are `LANDLOCK_ACCESS_FS_*` in Linux.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/landlock.h>.
+    pub(crate) struct FsAccess: u64 {
+        const EXECUTE     = 1 << 0;
+        const WRITE_FILE  = 1 << 1;
+        const READ_FILE   = 1 << 2;
+        const READ_DIR    = 1 << 3;
+        const REMOVE_DIR  = 1 << 4;
+        const REMOVE_FILE = 1 << 5;
+        const MAKE_CHAR   = 1 << 6;
+        const MAKE_DIR    = 1 << 7;
+        const MAKE_REG    = 1 << 8;
+        const MAKE_SOCK   = 1 << 9;
+        const MAKE_FIFO   = 1 << 10;
+        const MAKE_BLOCK  = 1 << 11;
+        const MAKE_SYM    = 1 << 12;
+        const REFER       = 1 << 13;
+        const TRUNCATE    = 1 << 14;
+    }
+}
+
+bitflags! {
+    /// The access rights to TCP ports, which are `LANDLOCK_ACCESS_NET_*` in Linux.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/landlock.h>.
+    pub(crate) struct NetAccess: u64 {
+        const BIND_TCP    = 1 << 0;
+        const CONNECT_TCP = 1 << 1;
+    }
+}
+
+impl FsAccess {
+    /// The access rights that can be granted to files that are not directories.
+    pub(super) const FILE: Self = Self::EXECUTE
+        .union(Self::WRITE_FILE)
+        .union(Self::READ_FILE)
+        .union(Self::TRUNCATE);
+
+    /// The access rights that are denied by default, even if they are not handled by a ruleset.
+    ///
+    /// Files cannot be moved to another directory unless a rule grants [`Self::REFER`] to both
+    /// directories.
+    pub(super) const INITIALLY_DENIED: Self = Self::REFER;
+
+    /// Returns the access right to make an inode of the type.
+    pub(super) fn make(type_: InodeType) -> Self {
+        match type_ {
+            InodeType::Dir => Self::MAKE_DIR,
+            InodeType::SymLink => Self::MAKE_SYM,
+            InodeType::CharDevice => Self::MAKE_CHAR,
+            InodeType::BlockDevice => Self::MAKE_BLOCK,
+            InodeType::NamedPipe => Self::MAKE_FIFO,
+            InodeType::Socket => Self::MAKE_SOCK,
+            InodeType::File | InodeType::Unknown => Self::MAKE_REG,
+        }
+    }
+
+    /// Returns the access right to remove an inode of the type.
+    pub(super) fn remove(type_: InodeType) -> Self {
+        if type_ == InodeType::Dir {
+            Self::REMOVE_DIR
+        } else {
+            Self::REMOVE_FILE
+        }
+    }
+}
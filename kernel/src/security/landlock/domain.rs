@@ -0,0 +1,159 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::{
+    access::{FsAccess, NetAccess},
+    ruleset::{InodeKey, Rules, Ruleset},
+};
+use crate::{fs::vfs::path::Path, prelude::*};
+
+/// The maximum number of the rulesets that can be enforced on a thread.
+const MAX_LAYERS: usize = 16;
+
+/// The domain of a thread, which consists of the rulesets enforced on the thread.
+///
+/// Each enforced ruleset is a layer of the domain. An access is allowed only if every layer
+/// that handles the access grants it.
+pub(crate) struct LandlockDomain {
+    /// The layers, from the oldest to the newest.
+    layers: Vec<Layer>,
+}
+
+/// A ruleset that has been enforced, whose rules can no longer change.
+#[derive(Clone)]
+struct Layer {
+    /// The handled access rights to files, including [`FsAccess::INITIALLY_DENIED`].
+    handled_fs: FsAccess,
+    handled_net: NetAccess,
+    rules: Rules,
+}
+
+impl LandlockDomain {
+    /// Creates a new domain that adds the ruleset as a layer on top of `parent`.
+    pub(super) fn new(parent: Option<&LandlockDomain>, ruleset: &Ruleset) -> Result<Self> {
+        let mut layers = parent.map_or_else(Vec::new, |parent| parent.layers.clone());
+        if layers.len() >= MAX_LAYERS {
+            return_errno_with_message!(Errno::E2BIG, "too many rulesets are enforced");
+        }
+
+        layers.push(Layer {
+            handled_fs: ruleset.handled_fs() | FsAccess::INITIALLY_DENIED,
+            handled_net: ruleset.handled_net(),
+            rules: ruleset.rules(),
+        });
+        Ok(Self { layers })
+    }
+
+    /// Returns whether the domain restricts the access to files.
+    pub(super) fn handles_fs(&self) -> bool {
+        self.layers
+            .iter()
+            .any(|layer| !(layer.handled_fs - FsAccess::INITIALLY_DENIED).is_empty())
+    }
+
+    /// Checks whether the access to the file at the path is allowed.
+    pub(super) fn check_path(&self, path: &Path, access: FsAccess) -> Result<()> {
+        if !self.handles_fs() {
+            return Ok(());
+        }
+
+        let granted = self.granted_fs_access(path);
+        let is_allowed = self
+            .layers
+            .iter()
+            .zip(granted.iter())
+            .all(|(layer, granted)| granted.contains(access & layer.handled_fs));
+        if !is_allowed {
+            return_errno_with_message!(Errno::EACCES, "the access is denied by Landlock");
+        }
+        Ok(())
+    }
+
+    /// Checks whether the file at `old` can be moved (or linked) from `old_dir` to `new_dir`.
+    ///
+    /// `old_access` and `new_access` are the access rights required on `old_dir` and `new_dir`,
+    /// respectively, such as the rights to remove and make the file.
+    ///
+    /// If the file is moved to another directory, both directories need to grant
+    /// [`FsAccess::REFER`]. In addition, the file cannot gain access rights in the new directory,
+    /// or the check fails with [`Errno::EXDEV`].
+    pub(super) fn check_reparent(
+        &self,
+        old_dir: &Path,
+        old: &Path,
+        old_access: FsAccess,
+        new_dir: &Path,
+        new_access: FsAccess,
+    ) -> Result<()> {
+        if !self.handles_fs() {
+            return Ok(());
+        }
+
+        if old_dir == new_dir {
+            return self.check_path(new_dir, old_access | new_access);
+        }
+
+        let old_granted = self.granted_fs_access(old_dir);
+        let new_granted = self.granted_fs_access(new_dir);
+        let file_granted = self.granted_fs_access(old);
+
+        let mut is_exdev = false;
+        for (i, layer) in self.layers.iter().enumerate() {
+            let (old_granted, new_granted) = (old_granted[i], new_granted[i]);
+            let old_missing = (old_access & layer.handled_fs) - old_granted;
+            let new_missing = (new_access & layer.handled_fs) - new_granted;
+            if !(old_missing | new_missing).is_empty() {
+                return_errno_with_message!(Errno::EACCES, "the access is denied by Landlock");
+            }
+
+            let has_refer = (old_granted & new_granted).contains(FsAccess::REFER);
+            let is_gaining = !file_granted[i].contains(new_granted);
+            is_exdev |= !has_refer || is_gaining;
+        }
+        if is_exdev {
+            return_errno_with_message!(
+                Errno::EXDEV,
+                "the file cannot be moved to another directory due to Landlock"
+            );
+        }
+        Ok(())
+    }
+
+    /// Checks whether the access to the TCP port is allowed.
+    pub(super) fn check_port(&self, port: u16, access: NetAccess) -> Result<()> {
+        let is_allowed = self.layers.iter().all(|layer| {
+            let required = access & layer.handled_net;
+            let granted = layer
+                .rules
+                .net
+                .get(&port)
+                .copied()
+                .unwrap_or(NetAccess::empty());
+            granted.contains(required)
+        });
+        if !is_allowed {
+            return_errno_with_message!(Errno::EACCES, "the access is denied by Landlock");
+        }
+        Ok(())
+    }
+
+    /// Returns, for each layer, the access rights that the layer grants to the file at the path.
+    ///
+    /// A rule grants the access rights to an inode and all its descendants, so the rules of the
+    /// path and all its ancestors are collected, crossing mount boundaries.
+    fn granted_fs_access(&self, path: &Path) -> [FsAccess; MAX_LAYERS] {
+        let mut granted = [FsAccess::empty(); MAX_LAYERS];
+
+        let mut current = Some(path.clone());
+        while let Some(path) = current {
+            let key = InodeKey::new(path.inode().as_ref());
+            for (layer, granted) in self.layers.iter().zip(granted.iter_mut()) {
+                if let Some(rule) = layer.rules.fs.get(&key) {
+                    *granted |= rule.access;
+                }
+            }
+            current = path.effective_parent();
+        }
+
+        granted
+    }
+}
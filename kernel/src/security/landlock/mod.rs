@@ -0,0 +1,173 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Landlock, a security module that allows unprivileged processes to sandbox themselves.
+//!
+//! A process creates a ruleset that handles some access rights, adds rules that grant the
+//! handled access rights to file hierarchies or TCP ports, and then enforces the ruleset on
+//! itself. The enforced rulesets form the domain of the thread, which is inherited by new threads
+//! and processes, and which can only be further restricted.
+//!
+//! Reference: <https://docs.kernel.org/userspace-api/landlock.html>.
+
+mod access;
+mod domain;
+mod ruleset;
+
+pub(crate) use self::{
+    access::{FsAccess, NetAccess},
+    domain::LandlockDomain,
+    ruleset::{Ruleset, RulesetFile},
+};
+use super::lsm::SecurityModule;
+use crate::{
+    fs::{
+        file::{AccessMode, FileLike, InodeType},
+        vfs::path::Path,
+    },
+    net::socket::{ip::StreamSocket, util::SocketAddr},
+    prelude::*,
+    process::posix_thread::{AsPosixThread, PosixThread},
+    thread::Thread,
+};
+
+/// The version of the Landlock ABI.
+///
+/// Version 4 supports the access rights to files (including [`FsAccess::REFER`] and
+/// [`FsAccess::TRUNCATE`]) and to TCP ports.
+pub(crate) const LANDLOCK_ABI_VERSION: u32 = 4;
+
+/// The Landlock security module.
+pub(super) struct Landlock;
+
+impl SecurityModule for Landlock {
+    fn name(&self) -> &'static str {
+        "landlock"
+    }
+
+    fn file_open(&self, path: &Path, access_mode: AccessMode) -> Result<()> {
+        let Some(domain) = current_domain() else {
+            return Ok(());
+        };
+
+        let mut access = FsAccess::empty();
+        if access_mode.is_readable() {
+            access |= if path.type_() == InodeType::Dir {
+                FsAccess::READ_DIR
+            } else {
+                FsAccess::READ_FILE
+            };
+        }
+        if access_mode.is_writable() {
+            access |= FsAccess::WRITE_FILE;
+        }
+        domain.check_path(path, access)
+    }
+
+    fn path_mknod(&self, dir: &Path, _name: &str, type_: InodeType) -> Result<()> {
+        let Some(domain) = current_domain() else {
+            return Ok(());
+        };
+        domain.check_path(dir, FsAccess::make(type_))
+    }
+
+    fn path_unlink(&self, dir: &Path, _name: &str) -> Result<()> {
+        let Some(domain) = current_domain() else {
+            return Ok(());
+        };
+        domain.check_path(dir, FsAccess::REMOVE_FILE)
+    }
+
+    fn path_rmdir(&self, dir: &Path, _name: &str) -> Result<()> {
+        let Some(domain) = current_domain() else {
+            return Ok(());
+        };
+        domain.check_path(dir, FsAccess::REMOVE_DIR)
+    }
+
+    fn path_link(&self, old: &Path, new_dir: &Path, _new_name: &str) -> Result<()> {
+        let Some(domain) = current_domain() else {
+            return Ok(());
+        };
+        let Some(old_dir) = old.effective_parent() else {
+            return Ok(());
+        };
+        let new_access = FsAccess::make(old.type_());
+        domain.check_reparent(&old_dir, old, FsAccess::empty(), new_dir, new_access)
+    }
+
+    fn path_rename(
+        &self,
+        old_dir: &Path,
+        old: &Path,
+        new_dir: &Path,
+        _new_name: &str,
+        new: Option<&Path>,
+    ) -> Result<()> {
+        let Some(domain) = current_domain() else {
+            return Ok(());
+        };
+        let old_access = FsAccess::remove(old.type_());
+        let mut new_access = FsAccess::make(old.type_());
+        if let Some(new) = new {
+            new_access |= FsAccess::remove(new.type_());
+        }
+        domain.check_reparent(old_dir, old, old_access, new_dir, new_access)
+    }
+
+    fn path_truncate(&self, path: &Path) -> Result<()> {
+        let Some(domain) = current_domain() else {
+            return Ok(());
+        };
+        domain.check_path(path, FsAccess::TRUNCATE)
+    }
+
+    fn bprm_check(&self, path: &Path) -> Result<()> {
+        let Some(domain) = current_domain() else {
+            return Ok(());
+        };
+        domain.check_path(path, FsAccess::EXECUTE)
+    }
+
+    fn socket_bind(&self, socket: &dyn FileLike, socket_addr: &SocketAddr) -> Result<()> {
+        check_tcp_port(socket, socket_addr, NetAccess::BIND_TCP)
+    }
+
+    fn socket_connect(&self, socket: &dyn FileLike, socket_addr: &SocketAddr) -> Result<()> {
+        check_tcp_port(socket, socket_addr, NetAccess::CONNECT_TCP)
+    }
+}
+
+/// Checks whether the access to the port of the address is allowed, if the socket is a TCP
+/// socket.
+fn check_tcp_port(
+    socket: &dyn FileLike,
+    socket_addr: &SocketAddr,
+    access: NetAccess,
+) -> Result<()> {
+    if socket.downcast_ref::<StreamSocket>().is_none() {
+        return Ok(());
+    }
+    let port = match socket_addr {
+        SocketAddr::IPv4(_, port) | SocketAddr::IPv6(_, port) => *port,
+        _ => return Ok(()),
+    };
+
+    let Some(domain) = current_domain() else {
+        return Ok(());
+    };
+    domain.check_port(port, access)
+}
+
+/// Returns the domain of the current thread.
+fn current_domain() -> Option<Arc<LandlockDomain>> {
+    let thread = Thread::current()?;
+    thread.as_posix_thread()?.landlock_domain()
+}
+
+/// Enforces the ruleset on the thread, which must be the current thread.
+pub(crate) fn restrict_self(posix_thread: &PosixThread, ruleset: &Ruleset) -> Result<()> {
+    let old_domain = posix_thread.landlock_domain();
+    let new_domain = LandlockDomain::new(old_domain.as_deref(), ruleset)?;
+    posix_thread.set_landlock_domain(Arc::new(new_domain));
+    Ok(())
+}
@@ -0,0 +1,196 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::collections::btree_map::BTreeMap;
+use core::fmt::Display;
+
+use super::access::{FsAccess, NetAccess};
+use crate::{
+    events::IoEvents,
+    fs::{
+        file::{CreationFlags, FileLike, InodeType, file_table::FdFlags},
+        pseudofs::AnonInodeFs,
+        vfs::{file_system::FileSystem, inode::Inode, path::Path},
+    },
+    prelude::*,
+    process::signal::{PollHandle, Pollable},
+};
+
+/// A ruleset, which is created by `landlock_create_ruleset`.
+///
+/// A ruleset handles some access rights, which are denied unless a rule grants them. It does not
+/// restrict any thread until it is enforced by `landlock_restrict_self`.
+pub(crate) struct Ruleset {
+    handled_fs: FsAccess,
+    handled_net: NetAccess,
+    rules: Mutex<Rules>,
+}
+
+/// The rules of a ruleset.
+#[derive(Clone, Default)]
+pub(super) struct Rules {
+    /// The rules on inodes, which grant access rights to the inodes and their descendants.
+    pub(super) fs: BTreeMap<InodeKey, FsRule>,
+    /// The rules on TCP ports.
+    pub(super) net: BTreeMap<u16, NetAccess>,
+}
+
+/// A rule on an inode.
+#[derive(Clone)]
+pub(super) struct FsRule {
+    pub(super) access: FsAccess,
+    /// The file system of the inode, which is held so that [`InodeKey`] is not reused.
+    _fs: Arc<dyn FileSystem>,
+}
+
+/// The key that identifies an inode.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) struct InodeKey {
+    fs: usize,
+    ino: u64,
+}
+
+impl InodeKey {
+    pub(super) fn new(inode: &dyn Inode) -> Self {
+        Self {
+            fs: Arc::as_ptr(&inode.fs()).cast::<()>() as usize,
+            ino: inode.ino(),
+        }
+    }
+}
+
+impl Ruleset {
+    /// Creates a new ruleset that handles the access rights.
+    pub(crate) fn new(handled_fs: FsAccess, handled_net: NetAccess) -> Result<Self> {
+        if handled_fs.is_empty() && handled_net.is_empty() {
+            return_errno_with_message!(Errno::ENOMSG, "the ruleset handles no access rights");
+        }
+
+        Ok(Self {
+            handled_fs,
+            handled_net,
+            rules: Mutex::new(Rules::default()),
+        })
+    }
+
+    /// Adds a rule that grants the access rights to the file at the path and its descendants.
+    pub(crate) fn add_path_rule(&self, path: &Path, access: FsAccess) -> Result<()> {
+        check_allowed_access(access.bits(), self.handled_fs.bits())?;
+        if path.type_() != InodeType::Dir && !FsAccess::FILE.contains(access) {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "the access rights cannot be granted to a file that is not a directory"
+            );
+        }
+
+        let inode = path.inode();
+        let mut rules = self.rules.lock();
+        rules
+            .fs
+            .entry(InodeKey::new(inode.as_ref()))
+            .or_insert_with(|| FsRule {
+                access: FsAccess::empty(),
+                _fs: inode.fs(),
+            })
+            .access |= access;
+        Ok(())
+    }
+
+    /// Adds a rule that grants the access rights to the TCP port.
+    pub(crate) fn add_port_rule(&self, port: u64, access: NetAccess) -> Result<()> {
+        check_allowed_access(access.bits(), self.handled_net.bits())?;
+        let port = u16::try_from(port)
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the port number is invalid"))?;
+
+        *self.rules.lock().net.entry(port).or_insert(NetAccess::empty()) |= access;
+        Ok(())
+    }
+
+    pub(super) fn handled_fs(&self) -> FsAccess {
+        self.handled_fs
+    }
+
+    pub(super) fn handled_net(&self) -> NetAccess {
+        self.handled_net
+    }
+
+    /// Returns a copy of the current rules.
+    pub(super) fn rules(&self) -> Rules {
+        self.rules.lock().clone()
+    }
+}
+
+/// Checks that a rule grants some access rights, all of which are handled by the ruleset.
+fn check_allowed_access(allowed: u64, handled: u64) -> Result<()> {
+    if allowed == 0 {
+        return_errno_with_message!(Errno::ENOMSG, "the rule grants no access rights");
+    }
+    if allowed & !handled != 0 {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "the rule grants access rights that are not handled by the ruleset"
+        );
+    }
+    Ok(())
+}
+
+/// The file of a ruleset.
+pub(crate) struct RulesetFile {
+    ruleset: Ruleset,
+    pseudo_path: Path,
+}
+
+impl RulesetFile {
+    pub(crate) fn new(ruleset: Ruleset) -> Self {
+        Self {
+            ruleset,
+            pseudo_path: AnonInodeFs::new_path(|_| "anon_inode:[landlock-ruleset]".to_string()),
+        }
+    }
+
+    /// Returns the ruleset.
+    pub(crate) fn ruleset(&self) -> &Ruleset {
+        &self.ruleset
+    }
+}
+
+impl Pollable for RulesetFile {
+    fn poll(&self, mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
+        (IoEvents::IN | IoEvents::OUT) & mask
+    }
+}
+
+impl FileLike for RulesetFile {
+    fn read(&self, _writer: &mut VmWriter) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "a ruleset cannot be read");
+    }
+
+    fn write(&self, _reader: &mut VmReader) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "a ruleset cannot be written");
+    }
+
+    fn path(&self) -> &Path {
+        &self.pseudo_path
+    }
+
+    fn dump_proc_fdinfo(self: Arc<Self>, fd_flags: FdFlags) -> Box<dyn Display> {
+        struct FdInfo {
+            flags: u32,
+        }
+
+        impl Display for FdInfo {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                writeln!(f, "pos:\t{}", 0)?;
+                writeln!(f, "flags:\t0{:o}", self.flags)?;
+                writeln!(f, "mnt_id:\t{}", AnonInodeFs::mount_node().id())?;
+                writeln!(f, "ino:\t{}", AnonInodeFs::shared_inode().ino())
+            }
+        }
+
+        let mut flags = self.status_flags().bits() | self.access_mode() as u32;
+        if fd_flags.contains(FdFlags::CLOEXEC) {
+            flags |= CreationFlags::O_CLOEXEC.bits();
+        }
+
+        Box::new(FdInfo { flags })
+    }
+}
@@ -0,0 +1,283 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The security hooks of Linux Security Modules (LSMs).
+//!
+//! The core kernel calls the hooks at the points where an access is about to be granted, after
+//! the discretionary access control (DAC) checks have passed. Each security module implements
+//! [`SecurityModule`] and overrides the hooks that it cares about. An access is granted only if
+//! all the security modules allow it, so a security module can restrict the accesses but can
+//! never grant an access that the DAC checks have denied.
+//!
+//! The security modules are registered at boot time and cannot be changed afterwards. Before
+//! that, every hook allows the access.
+//!
+//! Reference: <https://docs.kernel.org/security/lsm.html>.
+
+use spin::Once;
+
+use crate::{
+    fs::{
+        file::{AccessMode, FileLike, InodeType, Permission},
+        vfs::path::Path,
+    },
+    net::socket::util::SocketAddr,
+    prelude::*,
+    process::{
+        credentials::capabilities::CapSet,
+        posix_thread::{PosixThread, alien_access::AlienAccessMode},
+        signal::sig_num::SigNum,
+    },
+    util::net::{CSocketAddrFamily, SockType},
+};
+
+/// A security module.
+///
+/// Each hook returns `Ok(())` if the security module allows the operation, or the error that
+/// denies the operation (typically [`Errno::EACCES`] or [`Errno::EPERM`]). The default
+/// implementation of every hook allows the operation.
+pub(crate) trait SecurityModule: Sync {
+    /// Returns the name of the security module, such as `"yama"`.
+    fn name(&self) -> &'static str;
+
+    /// Checks whether the current thread may access the inode at the path with the permission.
+    fn inode_permission(&self, _path: &Path, _perm: Permission) -> Result<()> {
+        Ok(())
+    }
+
+    /// Checks whether the current thread may open the file at the path with the access mode.
+    fn file_open(&self, _path: &Path, _access_mode: AccessMode) -> Result<()> {
+        Ok(())
+    }
+
+    /// Checks whether the current thread may create an inode of the type in the directory.
+    ///
+    /// This hook is called for all types of inodes, including directories and symbolic links.
+    fn path_mknod(&self, _dir: &Path, _name: &str, _type_: InodeType) -> Result<()> {
+        Ok(())
+    }
+
+    /// Checks whether the current thread may unlink the file with the name in the directory.
+    fn path_unlink(&self, _dir: &Path, _name: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Checks whether the current thread may remove the directory with the name in the directory.
+    fn path_rmdir(&self, _dir: &Path, _name: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Checks whether the current thread may link the file at `old` into the directory.
+    fn path_link(&self, _old: &Path, _new_dir: &Path, _new_name: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Checks whether the current thread may rename the file at `old` in `old_dir` to `new_name`
+    /// in `new_dir`.
+    ///
+    /// If the new name exists in `new_dir`, `new` is the file that will be replaced.
+    fn path_rename(
+        &self,
+        _old_dir: &Path,
+        _old: &Path,
+        _new_dir: &Path,
+        _new_name: &str,
+        _new: Option<&Path>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Checks whether the current thread may truncate the file at the path.
+    fn path_truncate(&self, _path: &Path) -> Result<()> {
+        Ok(())
+    }
+
+    /// Checks whether the current thread may execute the file at the path.
+    ///
+    /// This hook is called for the executable and for each of its interpreters.
+    fn bprm_check(&self, _path: &Path) -> Result<()> {
+        Ok(())
+    }
+
+    /// Prepares the security attributes of the current thread for executing the program at the
+    /// path.
+    ///
+    /// This hook is called once for each `execve` before the point of no return, so it can still
+    /// deny the execution. The prepared attributes are applied by
+    /// [`Self::bprm_committing_creds`].
+    fn bprm_creds_for_exec(&self, _path: &Path) -> Result<()> {
+        Ok(())
+    }
+
+    /// Applies the security attributes prepared by [`Self::bprm_creds_for_exec`].
+    ///
+    /// This hook is called after the point of no return, so it cannot fail.
+    fn bprm_committing_creds(&self) {}
+
+    /// Checks whether the current thread may send the signal to the target thread.
+    ///
+    /// If `signum` is `None`, the current thread only checks whether the signal can be sent.
+    fn task_kill(&self, _target: &PosixThread, _signum: Option<SigNum>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Checks whether the thread may use the capability.
+    ///
+    /// This hook is called only if the thread has the capability.
+    fn capable(&self, _posix_thread: &PosixThread, _cap: CapSet) -> Result<()> {
+        Ok(())
+    }
+
+    /// Checks whether the accessor may access the resources of the target thread.
+    fn ptrace_access_check(
+        &self,
+        _accessor: &PosixThread,
+        _target: &PosixThread,
+        _mode: &AlienAccessMode,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Checks whether the current thread may create a socket.
+    fn socket_create(
+        &self,
+        _family: CSocketAddrFamily,
+        _type_: SockType,
+        _protocol: i32,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Checks whether the current thread may bind the socket to the address.
+    ///
+    /// `socket` is the file of the socket, so that the security module can find out the type of
+    /// the socket.
+    fn socket_bind(&self, _socket: &dyn FileLike, _socket_addr: &SocketAddr) -> Result<()> {
+        Ok(())
+    }
+
+    /// Checks whether the current thread may connect the socket to the address.
+    ///
+    /// `socket` is the file of the socket, as in [`Self::socket_bind`].
+    fn socket_connect(&self, _socket: &dyn FileLike, _socket_addr: &SocketAddr) -> Result<()> {
+        Ok(())
+    }
+}
+
+static MODULES: Once<Vec<&'static dyn SecurityModule>> = Once::new();
+
+/// Registers the security modules, whose hooks are called in the order of the modules.
+pub(super) fn init(modules: Vec<&'static dyn SecurityModule>) {
+    MODULES.call_once(|| modules);
+}
+
+/// Calls the hook of each security module until one of them denies the operation.
+fn call_hooks(hook: impl FnMut(&'static dyn SecurityModule) -> Result<()>) -> Result<()> {
+    let Some(modules) = MODULES.get() else {
+        return Ok(());
+    };
+    modules.iter().copied().try_for_each(hook)
+}
+
+/// Checks the security modules before the current thread accesses the inode at the path.
+///
+/// This function is called by [`Path::check_permission`] after the DAC checks pass.
+pub(crate) fn inode_permission(path: &Path, perm: Permission) -> Result<()> {
+    call_hooks(|module| module.inode_permission(path, perm))
+}
+
+/// Checks the security modules before the current thread opens the file at the path.
+pub(crate) fn file_open(path: &Path, access_mode: AccessMode) -> Result<()> {
+    call_hooks(|module| module.file_open(path, access_mode))
+}
+
+/// Checks the security modules before the current thread creates an inode in the directory.
+pub(crate) fn path_mknod(dir: &Path, name: &str, type_: InodeType) -> Result<()> {
+    call_hooks(|module| module.path_mknod(dir, name, type_))
+}
+
+/// Checks the security modules before the current thread unlinks a file in the directory.
+pub(crate) fn path_unlink(dir: &Path, name: &str) -> Result<()> {
+    call_hooks(|module| module.path_unlink(dir, name))
+}
+
+/// Checks the security modules before the current thread removes a directory in the directory.
+pub(crate) fn path_rmdir(dir: &Path, name: &str) -> Result<()> {
+    call_hooks(|module| module.path_rmdir(dir, name))
+}
+
+/// Checks the security modules before the current thread links a file into the directory.
+pub(crate) fn path_link(old: &Path, new_dir: &Path, new_name: &str) -> Result<()> {
+    call_hooks(|module| module.path_link(old, new_dir, new_name))
+}
+
+/// Checks the security modules before the current thread renames a file.
+pub(crate) fn path_rename(
+    old_dir: &Path,
+    old: &Path,
+    new_dir: &Path,
+    new_name: &str,
+    new: Option<&Path>,
+) -> Result<()> {
+    call_hooks(|module| module.path_rename(old_dir, old, new_dir, new_name, new))
+}
+
+/// Checks the security modules before the current thread truncates the file at the path.
+pub(crate) fn path_truncate(path: &Path) -> Result<()> {
+    call_hooks(|module| module.path_truncate(path))
+}
+
+/// Checks the security modules before the current thread executes the file at the path.
+pub(crate) fn bprm_check(path: &Path) -> Result<()> {
+    call_hooks(|module| module.bprm_check(path))
+}
+
+/// Prepares the security attributes of the current thread for executing the program at the path.
+pub(crate) fn bprm_creds_for_exec(path: &Path) -> Result<()> {
+    call_hooks(|module| module.bprm_creds_for_exec(path))
+}
+
+/// Applies the security attributes prepared by [`bprm_creds_for_exec`].
+pub(crate) fn bprm_committing_creds() {
+    let Some(modules) = MODULES.get() else {
+        return;
+    };
+    modules.iter().for_each(|module| module.bprm_committing_creds());
+}
+
+/// Checks the security modules before the current thread sends the signal to the target thread.
+pub(crate) fn task_kill(target: &PosixThread, signum: Option<SigNum>) -> Result<()> {
+    call_hooks(|module| module.task_kill(target, signum))
+}
+
+/// Checks the security modules before the thread uses the capability that it has.
+pub(crate) fn capable(posix_thread: &PosixThread, cap: CapSet) -> Result<()> {
+    call_hooks(|module| module.capable(posix_thread, cap))
+}
+
+/// Checks the security modules before the accessor accesses the resources of the target thread.
+pub(crate) fn ptrace_access_check(
+    accessor: &PosixThread,
+    target: &PosixThread,
+    mode: &AlienAccessMode,
+) -> Result<()> {
+    call_hooks(|module| module.ptrace_access_check(accessor, target, mode))
+}
+
+/// Checks the security modules before the current thread creates a socket.
+pub(crate) fn socket_create(
+    family: CSocketAddrFamily,
+    type_: SockType,
+    protocol: i32,
+) -> Result<()> {
+    call_hooks(|module| module.socket_create(family, type_, protocol))
+}
+
+/// Checks the security modules before the current thread binds the socket to the address.
+pub(crate) fn socket_bind(socket: &dyn FileLike, socket_addr: &SocketAddr) -> Result<()> {
+    call_hooks(|module| module.socket_bind(socket, socket_addr))
+}
+
+/// Checks the security modules before the current thread connects the socket to the address.
+pub(crate) fn socket_connect(socket: &dyn FileLike, socket_addr: &SocketAddr) -> Result<()> {
+    call_hooks(|module| module.socket_connect(socket, socket_addr))
+}
@@ -1,9 +1,18 @@
 // SPDX-License-Identifier: MPL-2.0
 
+pub(crate) mod apparmor;
+pub(crate) mod audit;
+pub(crate) mod keys;
+pub(crate) mod landlock;
+pub(crate) mod lsm;
 #[cfg(all(target_arch = "x86_64", feature = "cvm_guest"))]
 mod tsm;
+pub(crate) mod yama;
 
 pub(super) fn init() {
+    lsm::init(vec![&yama::Yama, &landlock::Landlock, &apparmor::AppArmor]);
+    apparmor::init();
+
     #[cfg(all(target_arch = "x86_64", feature = "cvm_guest"))]
     tsm::init();
 }
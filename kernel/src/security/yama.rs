@@ -0,0 +1,129 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Yama, a security module that restricts the scope of alien access (e.g., `ptrace`).
+//!
+//! Reference: <https://docs.kernel.org/admin-guide/LSM/Yama.html>.
+
+use alloc::format;
+use core::sync::atomic::{AtomicI32, Ordering};
+
+use super::{audit, lsm::SecurityModule};
+use crate::{
+    prelude::*,
+    process::{
+        Process, UserNamespace,
+        credentials::capabilities::CapSet,
+        posix_thread::{AsPosixThread, PosixThread, alien_access::AlienAccessMode},
+    },
+};
+
+/// The Yama security module.
+pub(super) struct Yama;
+
+impl SecurityModule for Yama {
+    fn name(&self) -> &'static str {
+        "yama"
+    }
+
+    // Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/security/yama/yama_lsm.c#L355>.
+    fn ptrace_access_check(
+        &self,
+        accessor: &PosixThread,
+        target: &PosixThread,
+        mode: &AlienAccessMode,
+    ) -> Result<()> {
+        if !mode.is_attach() {
+            return Ok(());
+        }
+
+        let has_cap = || {
+            target
+                .process()
+                .user_ns()
+                .lock()
+                .check_cap(CapSet::SYS_PTRACE, accessor)
+                .is_ok()
+        };
+        let is_denied = match get_yama_scope() {
+            YamaScope::Disabled => false,
+            YamaScope::Relational => {
+                !has_cap() && !is_ancestor_of(accessor.weak_process(), target.process())
+            }
+            YamaScope::Capability => !has_cap(),
+            YamaScope::NoAttach => true,
+        };
+
+        if is_denied {
+            let target_name = target.thread_name().lock().name().to_bytes().to_vec();
+            let object = format!(
+                "opid={} ocomm={}",
+                target.process().pid(),
+                audit::UntrustedStr(&target_name)
+            );
+            audit::log_denial(self.name(), "ptrace", &object);
+            return_errno_with_message!(Errno::EPERM, "alien access is denied due to Yama scope");
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the current Yama scope for alien access.
+pub(crate) fn get_yama_scope() -> YamaScope {
+    YAMA_SCOPE.load(Ordering::Relaxed).try_into().unwrap()
+}
+
+/// Sets the Yama scope for alien access.
+pub(crate) fn set_yama_scope(new_scope: YamaScope) -> Result<()> {
+    UserNamespace::get_init_singleton().check_cap(
+        CapSet::SYS_PTRACE,
+        current_thread!().as_posix_thread().unwrap(),
+    )?;
+
+    YAMA_SCOPE
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current_scope| {
+            let is_downgrading_from_no_attach =
+                current_scope == YamaScope::NoAttach as i32 && new_scope != YamaScope::NoAttach;
+            (!is_downgrading_from_no_attach).then_some(new_scope as i32)
+        })
+        .map_err(|_| {
+            Error::with_message(
+                Errno::EINVAL,
+                "`YamaScope::NoAttach` cannot be changed once set",
+            )
+        })?;
+
+    Ok(())
+}
+
+static YAMA_SCOPE: AtomicI32 = AtomicI32::new(YamaScope::Relational as i32);
+
+/// The Yama scope levels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+#[repr(i32)]
+pub(crate) enum YamaScope {
+    /// No additional restrictions on alien attach.
+    Disabled = 0,
+    /// Only allow alien attach by ancestor processes, or processes with `CapSet::SYS_PTRACE`.
+    Relational = 1,
+    /// Only allow alien attach by processes with `CapSet::SYS_PTRACE`.
+    Capability = 2,
+    /// Disallow any alien attach.
+    NoAttach = 3,
+}
+
+fn is_ancestor_of(ancestor: &Weak<Process>, descendant: Arc<Process>) -> bool {
+    let mut current = descendant;
+    loop {
+        let parent_guard = current.parent().lock();
+        let parent = parent_guard.process();
+        if Weak::ptr_eq(parent, ancestor) {
+            return true;
+        }
+        let Some(parent) = parent.upgrade() else {
+            return false;
+        };
+        drop(parent_guard);
+        current = parent;
+    }
+}
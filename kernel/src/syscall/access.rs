@@ -5,7 +5,7 @@ use crate::{
     fs::{
         file::{Permission, file_table::FileDesc},
         utils::PATH_MAX,
-        vfs::path::{AT_FDCWD, FsPath},
+        vfs::path::{AT_FDCWD, FsPath, PerMountFlags},
     },
     prelude::*,
 };
@@ -103,19 +103,21 @@ fn do_faccessat(
         return Ok(SyscallReturn::Return(0));
     }
 
-    let inode = path.inode();
-
     // FIXME: The current implementation is dummy
     if mode.contains(AccessMode::R_OK) {
-        inode.check_permission(Permission::MAY_READ)?;
+        path.check_permission(Permission::MAY_READ)?;
     }
 
     if mode.contains(AccessMode::W_OK) {
-        inode.check_permission(Permission::MAY_WRITE)?;
+        path.check_permission(Permission::MAY_WRITE)?;
+
+        if path.mount_flags().contains(PerMountFlags::RDONLY) && !path.type_().is_device() {
+            return_errno_with_message!(Errno::EROFS, "the mount is read-only");
+        }
     }
 
     if mode.contains(AccessMode::X_OK) {
-        inode.check_permission(Permission::MAY_EXEC)?;
+        path.check_permission(Permission::MAY_EXEC)?;
     }
 
     Ok(SyscallReturn::Return(0))
@@ -0,0 +1,61 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use ostd::mm::VmIo;
+
+use super::{ClockId, SyscallReturn};
+use crate::{
+    prelude::*,
+    process::credentials::capabilities::CapSet,
+    time::{
+        clockid_t,
+        ntp::{AdjModes, do_adjtimex, timex_t},
+    },
+};
+
+pub fn sys_adjtimex(timex_addr: Vaddr, ctx: &Context) -> Result<SyscallReturn> {
+    do_sys_adjtimex(timex_addr, ctx)
+}
+
+pub fn sys_clock_adjtime(
+    clockid: clockid_t,
+    timex_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!("clockid = {:?}", clockid);
+
+    // Like Linux, only the real-time clock can be adjusted.
+    if clockid < 0 || ClockId::try_from(clockid)? != ClockId::CLOCK_REALTIME {
+        return_errno_with_message!(Errno::EOPNOTSUPP, "the clock cannot be adjusted");
+    }
+
+    do_sys_adjtimex(timex_addr, ctx)
+}
+
+fn do_sys_adjtimex(timex_addr: Vaddr, ctx: &Context) -> Result<SyscallReturn> {
+    let user_space = ctx.user_space();
+    let mut timex = user_space.read_val::<timex_t>(timex_addr)?;
+    debug!("modes = {:#x}", timex.modes);
+
+    let modes = AdjModes::from_bits_truncate(timex.modes);
+    if !modes.is_read_only()
+        && !ctx
+            .posix_thread
+            .credentials()
+            .effective_capset()
+            .contains(CapSet::SYS_TIME)
+    {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "adjusting the clock requires the CAP_SYS_TIME capability"
+        );
+    }
+
+    let clock_state = do_adjtimex(&mut timex)?;
+    #[cfg(any(target_arch = "x86_64", target_arch = "riscv64"))]
+    if modes.contains(AdjModes::ADJ_TAI) {
+        crate::vdso::update_vdso_clock_offsets();
+    }
+    user_space.write_val(timex_addr, &timex)?;
+
+    Ok(SyscallReturn::Return(clock_state as _))
+}
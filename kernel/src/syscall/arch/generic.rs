@@ -13,7 +13,9 @@ macro_rules! import_generic_syscall_entries {
         use $crate::syscall::{
             accept::{sys_accept, sys_accept4},
             access::{sys_faccessat, sys_faccessat2},
+            adjtimex::{sys_adjtimex, sys_clock_adjtime},
             bind::sys_bind,
+            bpf::sys_bpf,
             brk::sys_brk,
             capget::sys_capget,
             capset::sys_capset,
@@ -63,7 +65,12 @@ macro_rules! import_generic_syscall_entries {
             getxattr::{sys_fgetxattr, sys_getxattr, sys_lgetxattr},
             inotify::{sys_inotify_add_watch, sys_inotify_init1, sys_inotify_rm_watch},
             ioctl::sys_ioctl,
+            kexec::{sys_kexec_file_load, sys_kexec_load},
+            keyctl::{sys_add_key, sys_keyctl, sys_request_key},
             kill::sys_kill,
+            landlock::{
+                sys_landlock_add_rule, sys_landlock_create_ruleset, sys_landlock_restrict_self,
+            },
             link::sys_linkat,
             listen::sys_listen,
             listxattr::{sys_flistxattr, sys_listxattr, sys_llistxattr},
@@ -74,12 +81,14 @@ macro_rules! import_generic_syscall_entries {
             mknod::sys_mknodat,
             mmap::sys_mmap,
             mount::sys_mount,
+            mount_setattr::sys_mount_setattr,
             mprotect::sys_mprotect,
             mremap::sys_mremap,
             msync::sys_msync,
             munmap::sys_munmap,
             nanosleep::{sys_clock_nanosleep, sys_nanosleep},
             open::sys_openat,
+            perf_event_open::sys_perf_event_open,
             pidfd_getfd::sys_pidfd_getfd,
             pidfd_open::sys_pidfd_open,
             pidfd_send_signal::sys_pidfd_send_signal,
@@ -93,10 +102,12 @@ macro_rules! import_generic_syscall_entries {
             pselect6::sys_pselect6,
             pwrite64::sys_pwrite64,
             pwritev::{sys_pwritev, sys_pwritev2, sys_writev},
+            quotactl::{sys_quotactl, sys_quotactl_fd},
             read::sys_read,
             readlink::sys_readlinkat,
             reboot::sys_reboot,
             recvfrom::sys_recvfrom,
+            recvmmsg::sys_recvmmsg,
             recvmsg::sys_recvmsg,
             removexattr::{sys_fremovexattr, sys_lremovexattr, sys_removexattr},
             rename::sys_renameat2,
@@ -155,6 +166,7 @@ macro_rules! import_generic_syscall_entries {
             symlink::sys_symlinkat,
             sync::{sys_sync, sys_syncfs},
             sysinfo::sys_sysinfo,
+            syslog::sys_syslog,
             tgkill::sys_tgkill,
             timer_create::{sys_timer_create, sys_timer_delete},
             timer_settime::{sys_timer_gettime, sys_timer_settime},
@@ -252,6 +264,7 @@ macro_rules! define_syscalls_with_generic_syscall_table {
             SYS_OPENAT = 56                  => sys_openat(args[..4]);
             SYS_CLOSE = 57                   => sys_close(args[..1]);
             SYS_PIPE2 = 59                   => sys_pipe2(args[..2]);
+            SYS_QUOTACTL = 60                => sys_quotactl(args[..4]);
             SYS_GETDENTS64 = 61              => sys_getdents64(args[..3]);
             SYS_LSEEK = 62                   => sys_lseek(args[..3]);
             SYS_READ = 63                    => sys_read(args[..3]);
@@ -288,12 +301,14 @@ macro_rules! define_syscalls_with_generic_syscall_table {
             SYS_NANOSLEEP = 101              => sys_nanosleep(args[..2]);
             SYS_GETITIMER = 102              => sys_getitimer(args[..2]);
             SYS_SETITIMER = 103              => sys_setitimer(args[..3]);
+            SYS_KEXEC_LOAD = 104             => sys_kexec_load(args[..4]);
             SYS_TIMER_CREATE = 107           => sys_timer_create(args[..3]);
             SYS_TIMER_GETTIME = 108          => sys_timer_gettime(args[..2]);
             SYS_TIMER_SETTIME = 110          => sys_timer_settime(args[..4]);
             SYS_TIMER_DELETE = 111           => sys_timer_delete(args[..1]);
             SYS_CLOCK_GETTIME = 113          => sys_clock_gettime(args[..2]);
             SYS_CLOCK_NANOSLEEP = 115        => sys_clock_nanosleep(args[..4]);
+            SYS_SYSLOG = 116                 => sys_syslog(args[..3]);
             SYS_SCHED_SETPARAM = 118         => sys_sched_setparam(args[..2]);
             SYS_SCHED_SETSCHEDULER = 119     => sys_sched_setscheduler(args[..3]);
             SYS_SCHED_GETSCHEDULER = 120     => sys_sched_getscheduler(args[..1]);
@@ -341,6 +356,7 @@ macro_rules! define_syscalls_with_generic_syscall_table {
             SYS_PRCTL = 167                  => sys_prctl(args[..5]);
             SYS_GETCPU = 168                 => sys_getcpu(args[..3]);
             SYS_GETTIMEOFDAY = 169           => sys_gettimeofday(args[..1]);
+            SYS_ADJTIMEX = 171               => sys_adjtimex(args[..1]);
             SYS_GETPID = 172                 => sys_getpid(args[..0]);
             SYS_GETPPID = 173                => sys_getppid(args[..0]);
             SYS_GETUID = 174                 => sys_getuid(args[..0]);
@@ -371,6 +387,9 @@ macro_rules! define_syscalls_with_generic_syscall_table {
             SYS_BRK = 214                    => sys_brk(args[..1]);
             SYS_MUNMAP = 215                 => sys_munmap(args[..2]);
             SYS_MREMAP = 216                 => sys_mremap(args[..5]);
+            SYS_ADD_KEY = 217                => sys_add_key(args[..5]);
+            SYS_REQUEST_KEY = 218            => sys_request_key(args[..4]);
+            SYS_KEYCTL = 219                 => sys_keyctl(args[..5]);
             SYS_CLONE = 220                  => sys_clone(args[..5], &user_ctx);
             SYS_EXECVE = 221                 => sys_execve(args[..3], &mut user_ctx);
             SYS_MMAP = 222                   => sys_mmap(args[..6]);
@@ -378,9 +397,12 @@ macro_rules! define_syscalls_with_generic_syscall_table {
             SYS_MPROTECT = 226               => sys_mprotect(args[..3]);
             SYS_MSYNC = 227                  => sys_msync(args[..3]);
             SYS_MADVISE = 233                => sys_madvise(args[..3]);
+            SYS_PERF_EVENT_OPEN = 241        => sys_perf_event_open(args[..5]);
             SYS_ACCEPT4 = 242                => sys_accept4(args[..4]);
+            SYS_RECVMMSG = 243               => sys_recvmmsg(args[..5]);
             SYS_WAIT4 = 260                  => sys_wait4(args[..4]);
             SYS_PRLIMIT64 = 261              => sys_prlimit64(args[..4]);
+            SYS_CLOCK_ADJTIME = 266          => sys_clock_adjtime(args[..2]);
             SYS_SYNCFS = 267                 => sys_syncfs(args[..1]);
             SYS_SETNS = 268                  => sys_setns(args[..2]);
             SYS_SENDMMSG = 269               => sys_sendmmsg(args[..4]);
@@ -389,10 +411,12 @@ macro_rules! define_syscalls_with_generic_syscall_table {
             SYS_RENAMEAT2 = 276              => sys_renameat2(args[..5]);
             SYS_GETRANDOM = 278              => sys_getrandom(args[..3]);
             SYS_MEMFD_CREATE = 279           => sys_memfd_create(args[..2]);
+            SYS_BPF = 280                    => sys_bpf(args[..3]);
             SYS_EXECVEAT = 281               => sys_execveat(args[..5], &mut user_ctx);
             SYS_PREADV2 = 286                => sys_preadv2(args[..6]);
             SYS_PWRITEV2 = 287               => sys_pwritev2(args[..6]);
             SYS_STATX = 291                  => sys_statx(args[..5]);
+            SYS_KEXEC_FILE_LOAD = 294        => sys_kexec_file_load(args[..5]);
             SYS_PIDFD_SEND_SIGNAL = 424      => sys_pidfd_send_signal(args[..4]);
             SYS_PIDFD_OPEN = 434             => sys_pidfd_open(args[..2]);
             SYS_CLONE3 = 435                 => sys_clone3(args[..2], &user_ctx);
@@ -400,6 +424,11 @@ macro_rules! define_syscalls_with_generic_syscall_table {
             SYS_PIDFD_GETFD = 438            => sys_pidfd_getfd(args[..3]);
             SYS_FACCESSAT2 = 439             => sys_faccessat2(args[..4]);
             SYS_EPOLL_PWAIT2 = 441           => sys_epoll_pwait2(args[..5]);
+            SYS_MOUNT_SETATTR = 442          => sys_mount_setattr(args[..5]);
+            SYS_QUOTACTL_FD = 443            => sys_quotactl_fd(args[..4]);
+            SYS_LANDLOCK_CREATE_RULESET = 444 => sys_landlock_create_ruleset(args[..3]);
+            SYS_LANDLOCK_ADD_RULE = 445      => sys_landlock_add_rule(args[..4]);
+            SYS_LANDLOCK_RESTRICT_SELF = 446 => sys_landlock_restrict_self(args[..2]);
             SYS_FCHMODAT2 = 452              => sys_fchmodat2(args[..4]);
             // Architecture-specific syscalls
             $( $name = $num => $handler $args );*
@@ -5,9 +5,11 @@
 use super::{
     accept::{sys_accept, sys_accept4},
     access::{sys_access, sys_faccessat, sys_faccessat2},
+    adjtimex::{sys_adjtimex, sys_clock_adjtime},
     alarm::sys_alarm,
     arch_prctl::sys_arch_prctl,
     bind::sys_bind,
+    bpf::sys_bpf,
     brk::sys_brk,
     capget::sys_capget,
     capset::sys_capset,
@@ -63,7 +65,12 @@ use super::{
     impl_syscall_nums_and_dispatch_fn,
     inotify::{sys_inotify_add_watch, sys_inotify_init, sys_inotify_init1, sys_inotify_rm_watch},
     ioctl::sys_ioctl,
+    kexec::{sys_kexec_file_load, sys_kexec_load},
+    keyctl::{sys_add_key, sys_keyctl, sys_request_key},
     kill::sys_kill,
+    landlock::{
+        sys_landlock_add_rule, sys_landlock_create_ruleset, sys_landlock_restrict_self,
+    },
     link::{sys_link, sys_linkat},
     listen::sys_listen,
     listxattr::{sys_flistxattr, sys_listxattr, sys_llistxattr},
@@ -74,6 +81,7 @@ use super::{
     mknod::{sys_mknod, sys_mknodat},
     mmap::sys_mmap,
     mount::sys_mount,
+    mount_setattr::sys_mount_setattr,
     mprotect::sys_mprotect,
     mremap::sys_mremap,
     msync::sys_msync,
@@ -81,6 +89,7 @@ use super::{
     nanosleep::{sys_clock_nanosleep, sys_nanosleep},
     open::{sys_creat, sys_open, sys_openat},
     pause::sys_pause,
+    perf_event_open::sys_perf_event_open,
     pidfd_getfd::sys_pidfd_getfd,
     pidfd_open::sys_pidfd_open,
     pidfd_send_signal::sys_pidfd_send_signal,
@@ -95,10 +104,12 @@ use super::{
     pselect6::sys_pselect6,
     pwrite64::sys_pwrite64,
     pwritev::{sys_pwritev, sys_pwritev2, sys_writev},
+    quotactl::{sys_quotactl, sys_quotactl_fd},
     read::sys_read,
     readlink::{sys_readlink, sys_readlinkat},
     reboot::sys_reboot,
     recvfrom::sys_recvfrom,
+    recvmmsg::sys_recvmmsg,
     recvmsg::sys_recvmsg,
     removexattr::{sys_fremovexattr, sys_lremovexattr, sys_removexattr},
     rename::{sys_rename, sys_renameat, sys_renameat2},
@@ -159,6 +170,7 @@ use super::{
     symlink::{sys_symlink, sys_symlinkat},
     sync::{sys_sync, sys_syncfs},
     sysinfo::sys_sysinfo,
+    syslog::sys_syslog,
     tgkill::sys_tgkill,
     time::sys_time,
     timer_create::{sys_timer_create, sys_timer_delete},
@@ -271,6 +283,7 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_GETRUSAGE = 98         => sys_getrusage(args[..2]);
     SYS_SYSINFO = 99           => sys_sysinfo(args[..1]);
     SYS_GETUID = 102           => sys_getuid(args[..0]);
+    SYS_SYSLOG = 103           => sys_syslog(args[..3]);
     SYS_GETGID = 104           => sys_getgid(args[..0]);
     SYS_SETUID = 105           => sys_setuid(args[..1]);
     SYS_SETGID = 106           => sys_setgid(args[..1]);
@@ -313,6 +326,7 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_PIVOT_ROOT = 155       => sys_pivot_root(args[..2]);
     SYS_PRCTL = 157            => sys_prctl(args[..5]);
     SYS_ARCH_PRCTL = 158       => sys_arch_prctl(args[..2], &mut user_ctx);
+    SYS_ADJTIMEX = 159         => sys_adjtimex(args[..1]);
     SYS_SETRLIMIT = 160        => sys_setrlimit(args[..2]);
     SYS_CHROOT = 161           => sys_chroot(args[..1]);
     SYS_SYNC = 162             => sys_sync(args[..0]);
@@ -321,6 +335,7 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_REBOOT = 169           => sys_reboot(args[..4]);
     SYS_SETHOSTNAME = 170      => sys_sethostname(args[..2]);
     SYS_SETDOMAINNAME = 171    => sys_setdomainname(args[..2]);
+    SYS_QUOTACTL = 179         => sys_quotactl(args[..4]);
     SYS_GETTID = 186           => sys_gettid(args[..0]);
     SYS_SETXATTR = 188         => sys_setxattr(args[..5]);
     SYS_LSETXATTR = 189        => sys_lsetxattr(args[..5]);
@@ -354,7 +369,11 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_EPOLL_CTL = 233        => sys_epoll_ctl(args[..4]);
     SYS_TGKILL = 234           => sys_tgkill(args[..3]);
     SYS_UTIMES = 235           => sys_utimes(args[..2]);
+    SYS_KEXEC_LOAD = 246       => sys_kexec_load(args[..4]);
     SYS_WAITID = 247           => sys_waitid(args[..5]);
+    SYS_ADD_KEY = 248          => sys_add_key(args[..5]);
+    SYS_REQUEST_KEY = 249      => sys_request_key(args[..4]);
+    SYS_KEYCTL = 250           => sys_keyctl(args[..5]);
     SYS_IOPRIO_SET = 251       => sys_ioprio_set(args[..3]);
     SYS_IOPRIO_GET = 252       => sys_ioprio_get(args[..2]);
     SYS_INOTIFY_INIT = 253     => sys_inotify_init(args[..0]);
@@ -394,7 +413,10 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_INOTIFY_INIT1 = 294     => sys_inotify_init1(args[..1]);
     SYS_PREADV = 295           => sys_preadv(args[..5]);
     SYS_PWRITEV = 296          => sys_pwritev(args[..5]);
+    SYS_PERF_EVENT_OPEN = 298  => sys_perf_event_open(args[..5]);
+    SYS_RECVMMSG = 299         => sys_recvmmsg(args[..5]);
     SYS_PRLIMIT64 = 302        => sys_prlimit64(args[..4]);
+    SYS_CLOCK_ADJTIME = 305    => sys_clock_adjtime(args[..2]);
     SYS_SYNCFS = 306           => sys_syncfs(args[..1]);
     SYS_SENDMMSG = 307         => sys_sendmmsg(args[..4]);
     SYS_SETNS = 308            => sys_setns(args[..2]);
@@ -404,6 +426,8 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_RENAMEAT2 = 316        => sys_renameat2(args[..5]);
     SYS_GETRANDOM = 318        => sys_getrandom(args[..3]);
     SYS_MEMFD_CREATE = 319     => sys_memfd_create(args[..2]);
+    SYS_KEXEC_FILE_LOAD = 320  => sys_kexec_file_load(args[..5]);
+    SYS_BPF = 321              => sys_bpf(args[..3]);
     SYS_EXECVEAT = 322         => sys_execveat(args[..5], &mut user_ctx);
     SYS_PREADV2 = 327          => sys_preadv2(args[..6]);
     SYS_PWRITEV2 = 328         => sys_pwritev2(args[..6]);
@@ -415,5 +439,10 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_PIDFD_GETFD = 438      => sys_pidfd_getfd(args[..3]);
     SYS_FACCESSAT2 = 439       => sys_faccessat2(args[..4]);
     SYS_EPOLL_PWAIT2 = 441     => sys_epoll_pwait2(args[..5]);
+    SYS_MOUNT_SETATTR = 442    => sys_mount_setattr(args[..5]);
+    SYS_QUOTACTL_FD = 443      => sys_quotactl_fd(args[..4]);
+    SYS_LANDLOCK_CREATE_RULESET = 444 => sys_landlock_create_ruleset(args[..3]);
+    SYS_LANDLOCK_ADD_RULE = 445 => sys_landlock_add_rule(args[..4]);
+    SYS_LANDLOCK_RESTRICT_SELF = 446 => sys_landlock_restrict_self(args[..2]);
     SYS_FCHMODAT2 = 452        => sys_fchmodat2(args[..4]);
 }
@@ -4,6 +4,7 @@ use super::SyscallReturn;
 use crate::{
     fs::file::file_table::{FileDesc, get_file_fast},
     prelude::*,
+    security::lsm,
     util::net::read_socket_addr_from_user,
 };
 
@@ -20,6 +21,7 @@ pub fn sys_bind(
     let file = get_file_fast!(&mut file_table, sockfd);
     let socket = file.as_socket_or_err()?;
 
+    lsm::socket_bind(&**file, &socket_addr)?;
     socket.bind(socket_addr)?;
 
     Ok(SyscallReturn::Return(0))
@@ -0,0 +1,266 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use ostd::mm::VmIo;
+
+use super::SyscallReturn;
+use crate::{
+    bpf::{
+        BPF_MAXINSNS, BpfInsn, BpfMapFile, BpfProg, BpfProgFile, BpfProgType,
+        map::{BpfMap, BpfMapAttr, BpfMapType, BpfUpdateMode, new_map},
+    },
+    fs::file::file_table::{FdFlags, FileDesc, get_file_fast},
+    prelude::*,
+    process::credentials::capabilities::CapSet,
+    util::CopyCompat,
+};
+
+pub fn sys_bpf(cmd: i32, attr_addr: Vaddr, size: u32, ctx: &Context) -> Result<SyscallReturn> {
+    debug!("cmd = {}, attr_addr = 0x{:x}, size = {}", cmd, attr_addr, size);
+
+    // TODO: Allow unprivileged users to load socket filters if the
+    // `kernel.unprivileged_bpf_disabled` sysctl is zero.
+    let effective_capset = ctx.posix_thread.credentials().effective_capset();
+    if !effective_capset.contains(CapSet::BPF) && !effective_capset.contains(CapSet::SYS_ADMIN) {
+        return_errno_with_message!(Errno::EPERM, "BPF requires CAP_BPF or CAP_SYS_ADMIN");
+    }
+
+    let size = size as usize;
+    if size > PAGE_SIZE {
+        return_errno_with_message!(Errno::E2BIG, "the attribute size is too large");
+    }
+
+    match BpfCmd::try_from(cmd)? {
+        BpfCmd::BPF_MAP_CREATE => map_create(attr_addr, size, ctx),
+        cmd @ (BpfCmd::BPF_MAP_LOOKUP_ELEM
+        | BpfCmd::BPF_MAP_UPDATE_ELEM
+        | BpfCmd::BPF_MAP_DELETE_ELEM
+        | BpfCmd::BPF_MAP_GET_NEXT_KEY) => map_elem_op(cmd, attr_addr, size, ctx),
+        BpfCmd::BPF_PROG_LOAD => prog_load(attr_addr, size, ctx),
+    }
+}
+
+fn map_create(attr_addr: Vaddr, size: usize, ctx: &Context) -> Result<SyscallReturn> {
+    let c_attr = ctx
+        .user_space()
+        .read_val_compat::<CBpfMapCreateAttr>(attr_addr, size)?;
+    debug!("map_create_attr = {:?}", c_attr);
+
+    if c_attr.inner_map_fd != 0 || c_attr.map_ifindex != 0 {
+        return_errno_with_message!(Errno::EINVAL, "inner maps and offloading are not supported");
+    }
+    check_obj_name(&c_attr.map_name)?;
+
+    let attr = BpfMapAttr {
+        map_type: BpfMapType::try_from(c_attr.map_type)?,
+        key_size: c_attr.key_size,
+        value_size: c_attr.value_size,
+        max_entries: c_attr.max_entries,
+        map_flags: c_attr.map_flags,
+    };
+    let map_file = BpfMapFile::new(new_map(attr)?);
+
+    let file_table = ctx.thread_local.borrow_file_table();
+    let fd = file_table
+        .unwrap()
+        .write()
+        .insert(Arc::new(map_file), FdFlags::CLOEXEC);
+    Ok(SyscallReturn::Return(fd as _))
+}
+
+fn map_elem_op(
+    cmd: BpfCmd,
+    attr_addr: Vaddr,
+    size: usize,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let c_attr = ctx
+        .user_space()
+        .read_val_compat::<CBpfMapElemAttr>(attr_addr, size)?;
+    debug!("map_elem_attr = {:?}", c_attr);
+
+    let map = get_map(c_attr.map_fd as FileDesc, ctx)?;
+    let attr = map.attr();
+    let user_space = ctx.user_space();
+
+    // The key of `BPF_MAP_GET_NEXT_KEY` can be null, which means the first key is requested.
+    let key = if c_attr.key == 0 && cmd == BpfCmd::BPF_MAP_GET_NEXT_KEY {
+        None
+    } else {
+        let mut key = vec![0; attr.key_size as usize];
+        user_space.read_bytes(c_attr.key as Vaddr, &mut key)?;
+        Some(key)
+    };
+
+    if cmd != BpfCmd::BPF_MAP_UPDATE_ELEM && c_attr.flags != 0 {
+        return_errno_with_message!(Errno::EINVAL, "the flags are invalid");
+    }
+
+    match cmd {
+        BpfCmd::BPF_MAP_LOOKUP_ELEM => {
+            let mut value = vec![0; attr.value_size as usize];
+            map.lookup(key.as_ref().unwrap(), &mut value)?;
+            user_space.write_bytes(c_attr.value as Vaddr, &value)?;
+        }
+        BpfCmd::BPF_MAP_UPDATE_ELEM => {
+            let mode = BpfUpdateMode::from_flags(c_attr.flags)?;
+            let mut value = vec![0; attr.value_size as usize];
+            user_space.read_bytes(c_attr.value as Vaddr, &mut value)?;
+            map.update(key.as_ref().unwrap(), &value, mode)?;
+        }
+        BpfCmd::BPF_MAP_DELETE_ELEM => map.delete(key.as_ref().unwrap())?,
+        BpfCmd::BPF_MAP_GET_NEXT_KEY => {
+            let mut next_key = vec![0; attr.key_size as usize];
+            map.get_next_key(key.as_deref(), &mut next_key)?;
+            user_space.write_bytes(c_attr.value as Vaddr, &next_key)?;
+        }
+        _ => unreachable!(),
+    }
+
+    Ok(SyscallReturn::Return(0))
+}
+
+fn prog_load(attr_addr: Vaddr, size: usize, ctx: &Context) -> Result<SyscallReturn> {
+    let c_attr = ctx
+        .user_space()
+        .read_val_compat::<CBpfProgLoadAttr>(attr_addr, size)?;
+    debug!("prog_load_attr = {:?}", c_attr);
+
+    let prog_type = BpfProgType::try_from(c_attr.prog_type)?;
+    if c_attr.prog_flags != 0 || c_attr.prog_ifindex != 0 {
+        return_errno_with_message!(Errno::EINVAL, "the program flags are not supported");
+    }
+    check_obj_name(&c_attr.prog_name)?;
+
+    // None of the supported helpers is GPL-only, so the license only needs to be readable.
+    ctx.user_space()
+        .read_cstring(c_attr.license as Vaddr, BPF_LICENSE_LEN)?;
+
+    let has_log = c_attr.log_level != 0 || c_attr.log_buf != 0 || c_attr.log_size != 0;
+    if has_log {
+        if c_attr.log_buf == 0
+            || c_attr.log_size < BPF_LOG_MIN_SIZE
+            || c_attr.log_level == 0
+            || c_attr.log_level & !BPF_LOG_LEVEL_MASK != 0
+        {
+            return_errno_with_message!(Errno::EINVAL, "the log attributes are invalid");
+        }
+        // TODO: Write the verifier messages to the log. For now, the log is always empty.
+        ctx.user_space().write_val(c_attr.log_buf as Vaddr, &0u8)?;
+    }
+
+    let insn_cnt = c_attr.insn_cnt as usize;
+    if insn_cnt > BPF_MAXINSNS {
+        return_errno_with_message!(Errno::E2BIG, "the program has too many instructions");
+    }
+    let mut insns = vec![BpfInsn::new_zeroed(); insn_cnt];
+    ctx.user_space()
+        .read_slice(c_attr.insns as Vaddr, &mut insns)?;
+
+    let prog = BpfProg::load(prog_type, insns.into_boxed_slice(), |fd| get_map(fd, ctx))?;
+    let prog_file = BpfProgFile::new(Arc::new(prog));
+
+    let file_table = ctx.thread_local.borrow_file_table();
+    let fd = file_table
+        .unwrap()
+        .write()
+        .insert(Arc::new(prog_file), FdFlags::CLOEXEC);
+    Ok(SyscallReturn::Return(fd as _))
+}
+
+fn get_map(fd: FileDesc, ctx: &Context) -> Result<Arc<dyn BpfMap>> {
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+    let file = get_file_fast!(&mut file_table, fd);
+    let Some(map_file) = file.downcast_ref::<BpfMapFile>() else {
+        return_errno_with_message!(Errno::EINVAL, "the file is not a BPF map");
+    };
+
+    Ok(map_file.map().clone())
+}
+
+/// Checks the name of a map or a program, which can only contain alphanumeric characters,
+/// underscores, and dots.
+fn check_obj_name(name: &[u8; BPF_OBJ_NAME_LEN]) -> Result<()> {
+    let Some(len) = name.iter().position(|byte| *byte == 0) else {
+        return_errno_with_message!(Errno::EINVAL, "the name is not null-terminated");
+    };
+    let is_valid = name[..len]
+        .iter()
+        .all(|byte| byte.is_ascii_alphanumeric() || *byte == b'_' || *byte == b'.');
+    if !is_valid {
+        return_errno_with_message!(Errno::EINVAL, "the name contains invalid characters");
+    }
+
+    Ok(())
+}
+
+const BPF_OBJ_NAME_LEN: usize = 16;
+const BPF_LICENSE_LEN: usize = 128;
+
+const BPF_LOG_MIN_SIZE: u32 = 128;
+/// The valid log levels (`BPF_LOG_LEVEL1`, `BPF_LOG_LEVEL2`, and `BPF_LOG_STATS`).
+const BPF_LOG_LEVEL_MASK: u32 = 0b111;
+
+/// The commands of the `bpf` system call.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/bpf.h#L838>.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+#[expect(non_camel_case_types)]
+enum BpfCmd {
+    BPF_MAP_CREATE = 0,
+    BPF_MAP_LOOKUP_ELEM = 1,
+    BPF_MAP_UPDATE_ELEM = 2,
+    BPF_MAP_DELETE_ELEM = 3,
+    BPF_MAP_GET_NEXT_KEY = 4,
+    BPF_PROG_LOAD = 5,
+}
+
+/// The attributes of `BPF_MAP_CREATE`.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/bpf.h#L1283>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CBpfMapCreateAttr {
+    map_type: u32,
+    key_size: u32,
+    value_size: u32,
+    max_entries: u32,
+    map_flags: u32,
+    inner_map_fd: u32,
+    _numa_node: u32,
+    map_name: [u8; BPF_OBJ_NAME_LEN],
+    map_ifindex: u32,
+}
+
+/// The attributes of `BPF_MAP_*_ELEM` and `BPF_MAP_GET_NEXT_KEY`.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/bpf.h#L1318>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CBpfMapElemAttr {
+    map_fd: u32,
+    _pad: u32,
+    key: u64,
+    /// The value, or the next key for `BPF_MAP_GET_NEXT_KEY`.
+    value: u64,
+    flags: u64,
+}
+
+/// The attributes of `BPF_PROG_LOAD`.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/bpf.h#L1338>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CBpfProgLoadAttr {
+    prog_type: u32,
+    insn_cnt: u32,
+    insns: u64,
+    license: u64,
+    log_level: u32,
+    log_size: u32,
+    log_buf: u64,
+    _kern_version: u32,
+    prog_flags: u32,
+    prog_name: [u8; BPF_OBJ_NAME_LEN],
+    prog_ifindex: u32,
+}
@@ -16,7 +16,7 @@ use crate::{
         Clock, clockid_t,
         clocks::{
             BootTimeClock, MonotonicClock, MonotonicCoarseClock, MonotonicRawClock, RealTimeClock,
-            RealTimeCoarseClock,
+            RealTimeCoarseClock, TaiClock,
         },
         timespec_t,
     },
@@ -50,6 +50,9 @@ pub enum ClockId {
     CLOCK_REALTIME_COARSE = 5,
     CLOCK_MONOTONIC_COARSE = 6,
     CLOCK_BOOTTIME = 7,
+    CLOCK_REALTIME_ALARM = 8,
+    CLOCK_BOOTTIME_ALARM = 9,
+    CLOCK_TAI = 11,
 }
 
 /// The information decoded from a dynamic clock ID.
@@ -116,12 +119,17 @@ pub fn read_clock(clockid: clockid_t, ctx: &Context) -> Result<Duration> {
     if clockid >= 0 {
         let clock_id = ClockId::try_from(clockid)?;
         match clock_id {
-            ClockId::CLOCK_REALTIME => Ok(RealTimeClock::get().read_time()),
+            ClockId::CLOCK_REALTIME | ClockId::CLOCK_REALTIME_ALARM => {
+                Ok(RealTimeClock::get().read_time())
+            }
             ClockId::CLOCK_MONOTONIC => Ok(MonotonicClock::get().read_time()),
             ClockId::CLOCK_MONOTONIC_RAW => Ok(MonotonicRawClock::get().read_time()),
             ClockId::CLOCK_REALTIME_COARSE => Ok(RealTimeCoarseClock::get().read_time()),
             ClockId::CLOCK_MONOTONIC_COARSE => Ok(MonotonicCoarseClock::get().read_time()),
-            ClockId::CLOCK_BOOTTIME => Ok(BootTimeClock::get().read_time()),
+            ClockId::CLOCK_BOOTTIME | ClockId::CLOCK_BOOTTIME_ALARM => {
+                Ok(BootTimeClock::get().read_time())
+            }
+            ClockId::CLOCK_TAI => Ok(TaiClock::get().read_time()),
             ClockId::CLOCK_PROCESS_CPUTIME_ID => Ok(ctx.process.prof_clock().read_time()),
             ClockId::CLOCK_THREAD_CPUTIME_ID => Ok(ctx.posix_thread.prof_clock().read_time()),
         }
@@ -6,11 +6,13 @@ use super::SyscallReturn;
 use crate::{
     fs::{
         file::{
-            FileLike, StatusFlags,
+            FileLike, InodeHandle, StatusFlags,
             file_table::{FdFlags, FileDesc, WithFileTable, get_file_fast},
         },
         ramfs::memfd::{FileSeals, MemfdInodeHandle},
-        vfs::range_lock::{FileRange, OFFSET_MAX, RangeLockItem, RangeLockType},
+        vfs::range_lock::{
+            FileRange, OFFSET_MAX, RangeLockItem, RangeLockOwner, RangeLockType, RangeLockWaitMode,
+        },
     },
     prelude::*,
     process::{Pid, process_table},
@@ -26,14 +28,48 @@ pub fn sys_fcntl(fd: FileDesc, cmd: i32, arg: u64, ctx: &Context) -> Result<Sysc
         FcntlCmd::F_SETFD => handle_setfd(fd, arg, ctx),
         FcntlCmd::F_GETFL => handle_getfl(fd, ctx),
         FcntlCmd::F_SETFL => handle_setfl(fd, arg, ctx),
-        FcntlCmd::F_GETLK => handle_getlk(fd, arg, ctx),
-        FcntlCmd::F_SETLK => handle_setlk(fd, arg, true, ctx),
-        FcntlCmd::F_SETLKW => handle_setlk(fd, arg, false, ctx).map_err(|err| match err.error() {
+        FcntlCmd::F_GETLK => handle_getlk(fd, arg, LockOwnerKind::Process, ctx),
+        FcntlCmd::F_SETLK => handle_setlk(
+            fd,
+            arg,
+            RangeLockWaitMode::NoWait,
+            LockOwnerKind::Process,
+            ctx,
+        ),
+        FcntlCmd::F_SETLKW => handle_setlk(
+            fd,
+            arg,
+            RangeLockWaitMode::Wait,
+            LockOwnerKind::Process,
+            ctx,
+        )
+        .map_err(|err| match err.error() {
+            Errno::EINTR => Error::new(Errno::ERESTARTSYS),
+            _ => err,
+        }),
+        FcntlCmd::F_OFD_GETLK => handle_getlk(fd, arg, LockOwnerKind::OpenFile, ctx),
+        FcntlCmd::F_OFD_SETLK => handle_setlk(
+            fd,
+            arg,
+            RangeLockWaitMode::NoWait,
+            LockOwnerKind::OpenFile,
+            ctx,
+        ),
+        FcntlCmd::F_OFD_SETLKW => handle_setlk(
+            fd,
+            arg,
+            RangeLockWaitMode::Wait,
+            LockOwnerKind::OpenFile,
+            ctx,
+        )
+        .map_err(|err| match err.error() {
             Errno::EINTR => Error::new(Errno::ERESTARTSYS),
             _ => err,
         }),
         FcntlCmd::F_GETOWN => handle_getown(fd, ctx),
         FcntlCmd::F_SETOWN => handle_setown(fd, arg, ctx),
+        FcntlCmd::F_SETLEASE => handle_setlease(fd, arg, ctx),
+        FcntlCmd::F_GETLEASE => handle_getlease(fd, ctx),
         FcntlCmd::F_ADD_SEALS => handle_addseal(fd, arg, ctx),
         FcntlCmd::F_GET_SEALS => handle_getseal(fd, ctx),
     }
@@ -94,7 +130,12 @@ fn handle_setfl(fd: FileDesc, arg: u64, ctx: &Context) -> Result<SyscallReturn>
     Ok(SyscallReturn::Return(0))
 }
 
-fn handle_getlk(fd: FileDesc, arg: u64, ctx: &Context) -> Result<SyscallReturn> {
+fn handle_getlk(
+    fd: FileDesc,
+    arg: u64,
+    owner_kind: LockOwnerKind,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
     let mut file_table = ctx.thread_local.borrow_file_table_mut();
     let file = get_file_fast!(&mut file_table, fd);
     let lock_mut_ptr = arg as Vaddr;
@@ -103,8 +144,10 @@ fn handle_getlk(fd: FileDesc, arg: u64, ctx: &Context) -> Result<SyscallReturn>
     if lock_type == RangeLockType::Unlock {
         return_errno_with_message!(Errno::EINVAL, "invalid flock type for getlk");
     }
-    let mut lock = RangeLockItem::new(lock_type, from_c_flock_and_file(&lock_mut_c, &**file)?);
+    let range = from_c_flock_and_file(&lock_mut_c, &**file)?;
     let inode_file = file.as_inode_handle_or_err()?;
+    let owner = owner_kind.owner_of(&lock_mut_c, inode_file, ctx)?;
+    let mut lock = RangeLockItem::new_with_owner(lock_type, range, owner);
     lock = inode_file.test_range_lock(lock)?;
     lock_mut_c.copy_from_range_lock(&lock);
     ctx.user_space().write_val(lock_mut_ptr, &lock_mut_c)?;
@@ -114,7 +157,8 @@ fn handle_getlk(fd: FileDesc, arg: u64, ctx: &Context) -> Result<SyscallReturn>
 fn handle_setlk(
     fd: FileDesc,
     arg: u64,
-    is_nonblocking: bool,
+    wait_mode: RangeLockWaitMode,
+    owner_kind: LockOwnerKind,
     ctx: &Context,
 ) -> Result<SyscallReturn> {
     let mut file_table = ctx.thread_local.borrow_file_table_mut();
@@ -122,12 +166,49 @@ fn handle_setlk(
     let lock_mut_ptr = arg as Vaddr;
     let lock_mut_c = ctx.user_space().read_val::<c_flock>(lock_mut_ptr)?;
     let lock_type = RangeLockType::try_from(lock_mut_c.l_type)?;
-    let lock = RangeLockItem::new(lock_type, from_c_flock_and_file(&lock_mut_c, &**file)?);
+    let range = from_c_flock_and_file(&lock_mut_c, &**file)?;
     let inode_file = file.as_inode_handle_or_err()?;
-    inode_file.set_range_lock(&lock, is_nonblocking)?;
+    let owner = owner_kind.owner_of(&lock_mut_c, inode_file, ctx)?;
+    let lock = RangeLockItem::new_with_owner(lock_type, range, owner);
+    inode_file.set_range_lock(&lock, wait_mode)?;
     Ok(SyscallReturn::Return(0))
 }
 
+/// The kind of the owner of the record locks manipulated by a `fcntl` command.
+#[derive(Debug, Clone, Copy)]
+enum LockOwnerKind {
+    /// The traditional record locks owned by the process (`F_GETLK`, `F_SETLK` and `F_SETLKW`).
+    Process,
+    /// The locks owned by the open file description (`F_OFD_GETLK`, `F_OFD_SETLK` and
+    /// `F_OFD_SETLKW`).
+    OpenFile,
+}
+
+impl LockOwnerKind {
+    /// Returns the owner of the lock described by the C flock on the file.
+    ///
+    /// The `l_pid` of an open file description lock must be zero.
+    fn owner_of(
+        self,
+        lock: &c_flock,
+        inode_file: &InodeHandle,
+        ctx: &Context,
+    ) -> Result<RangeLockOwner> {
+        match self {
+            Self::Process => Ok(RangeLockOwner::Process(ctx.process.pid())),
+            Self::OpenFile => {
+                if lock.l_pid != 0 {
+                    return_errno_with_message!(
+                        Errno::EINVAL,
+                        "the pid of an OFD lock must be zero"
+                    );
+                }
+                Ok(inode_file.ofd_lock_owner())
+            }
+        }
+    }
+}
+
 fn handle_getown(fd: FileDesc, ctx: &Context) -> Result<SyscallReturn> {
     let mut file_table = ctx.thread_local.borrow_file_table_mut();
     file_table.read_with(|inner| {
@@ -161,6 +242,33 @@ fn handle_setown(fd: FileDesc, arg: u64, ctx: &Context) -> Result<SyscallReturn>
     Ok(SyscallReturn::Return(0))
 }
 
+fn handle_setlease(fd: FileDesc, arg: u64, ctx: &Context) -> Result<SyscallReturn> {
+    let lease_type = u16::try_from(arg)
+        .ok()
+        .and_then(|arg| RangeLockType::try_from(arg).ok())
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid lease type"))?;
+
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+    // The lease holder is notified through the owner of the file, which defaults to the
+    // calling process.
+    let owner = file_table.read_with(|inner| inner.get_entry(fd).map(|entry| entry.owner()))?;
+    let process = owner
+        .and_then(process_table::get_process)
+        .unwrap_or_else(|| ctx.process.clone());
+
+    let file = get_file_fast!(&mut file_table, fd);
+    file.as_inode_handle_or_err()?
+        .set_lease(lease_type, Arc::downgrade(&process))?;
+    Ok(SyscallReturn::Return(0))
+}
+
+fn handle_getlease(fd: FileDesc, ctx: &Context) -> Result<SyscallReturn> {
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+    let file = get_file_fast!(&mut file_table, fd);
+    let lease_type = file.as_inode_handle_or_err()?.get_lease()?;
+    Ok(SyscallReturn::Return(lease_type as _))
+}
+
 fn handle_addseal(fd: FileDesc, arg: u64, ctx: &Context) -> Result<SyscallReturn> {
     let new_seals = FileSeals::from_bits(arg as u32)
         .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid seals"))?;
@@ -196,6 +304,11 @@ enum FcntlCmd {
     F_SETLKW = 7,
     F_SETOWN = 8,
     F_GETOWN = 9,
+    F_OFD_GETLK = 36,
+    F_OFD_SETLK = 37,
+    F_OFD_SETLKW = 38,
+    F_SETLEASE = 1024,
+    F_GETLEASE = 1025,
     F_DUPFD_CLOEXEC = 1030,
     F_ADD_SEALS = 1033,
     F_GET_SEALS = 1034,
@@ -241,7 +354,11 @@ impl c_flock {
             } else {
                 lock.range().len() as off_t
             };
-            self.l_pid = lock.owner();
+            // The owner of an open file description lock is reported as -1.
+            self.l_pid = match lock.owner() {
+                RangeLockOwner::Process(pid) => pid,
+                RangeLockOwner::OpenFile(_) => -1i32 as Pid,
+            };
         }
     }
 }
@@ -5,6 +5,7 @@ use ostd::mm::VmIo;
 use super::SyscallReturn;
 use crate::{
     fs::file::file_table::{FileDesc, get_file_fast},
+    net::{netfilter, socket::util::SocketAddr},
     prelude::*,
     util::net::{CSocketOptionLevel, new_raw_socket_option},
 };
@@ -31,6 +32,16 @@ pub fn sys_getsockopt(
     let file = get_file_fast!(&mut file_table, sockfd);
     let socket = file.as_socket_or_err()?;
 
+    // The iptables options share the same names between `getsockopt` and `setsockopt`, and some
+    // of them read input from `optval`, so they are handled separately.
+    if level == CSocketOptionLevel::SOL_IP
+        && netfilter::is_iptables_option(optname)
+        && matches!(socket.addr(), Ok(SocketAddr::IPv4(..)))
+    {
+        netfilter::get_iptables_option(optname, optval, optlen)?;
+        return Ok(SyscallReturn::Return(0));
+    }
+
     let mut raw_option = new_raw_socket_option(level, optname)?;
     debug!("raw option: {:?}", raw_option);
 
@@ -0,0 +1,278 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::ops::Range;
+
+use ostd::power::{KEXEC_MAX_SEGMENTS, KexecImage, can_kexec, crash_kernel_region};
+
+use super::SyscallReturn;
+use crate::{
+    arch::kexec::{KEXEC_ARCH, load_crash_kernel_image, load_kernel_image},
+    fs::file::{
+        FileLike,
+        file_table::{FileDesc, WithFileTable},
+    },
+    power::kexec::{load_crash_image, load_image, unload_crash_image, unload_image},
+    prelude::*,
+    process::credentials::capabilities::CapSet,
+};
+
+pub fn sys_kexec_load(
+    entry: Vaddr,
+    nr_segments: usize,
+    segments_addr: Vaddr,
+    flags: u64,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!(
+        "entry = {:#x}, nr_segments = {}, segments_addr = {:#x}, flags = {:#x}",
+        entry, nr_segments, segments_addr, flags
+    );
+
+    check_kexec_supported()?;
+    check_sys_boot(ctx)?;
+
+    let arch = flags as u32 & KEXEC_ARCH_MASK;
+    if arch != KEXEC_ARCH_DEFAULT && arch != KEXEC_ARCH {
+        return_errno_with_message!(Errno::EINVAL, "the architecture is not supported");
+    }
+    let flags = KexecLoadFlags::from_bits(flags as u32 & !KEXEC_ARCH_MASK)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "the flags are invalid"))?;
+    if flags.contains(KexecLoadFlags::PRESERVE_CONTEXT) {
+        return_errno_with_message!(Errno::EINVAL, "preserving the context is not supported");
+    }
+    let on_crash = flags.contains(KexecLoadFlags::ON_CRASH);
+
+    if nr_segments > KEXEC_MAX_SEGMENTS {
+        return_errno_with_message!(Errno::EINVAL, "there are too many segments");
+    }
+    if nr_segments == 0 {
+        if on_crash {
+            unload_crash_image();
+        } else {
+            unload_image();
+        }
+        return Ok(SyscallReturn::Return(0));
+    }
+
+    let user_space = ctx.user_space();
+    // The number of segments is checked above, so the size cannot overflow.
+    let mut segments_reader =
+        user_space.reader(segments_addr, nr_segments * size_of::<CKexecSegment>())?;
+    let mut segments = Vec::with_capacity(nr_segments);
+    for _ in 0..nr_segments {
+        segments.push(segments_reader.read_val::<CKexecSegment>()?);
+    }
+
+    let mut dests = Vec::with_capacity(nr_segments);
+    for segment in segments.iter() {
+        if segment.bufsz > segment.memsz {
+            return_errno_with_message!(Errno::EINVAL, "the buffer is larger than the memory");
+        }
+        if segment.mem % PAGE_SIZE != 0 || segment.memsz % PAGE_SIZE != 0 {
+            return_errno_with_message!(Errno::EADDRNOTAVAIL, "the memory is not page-aligned");
+        }
+        let dest_end = segment
+            .mem
+            .checked_add(segment.memsz)
+            .ok_or_else(|| Error::with_message(Errno::EADDRNOTAVAIL, "the memory overflows"))?;
+        dests.push(segment.mem..dest_end);
+    }
+
+    if on_crash {
+        check_crash_dests(&dests)?;
+        load_crash_image(|| {
+            let image = KexecImage::new_crash(&dests, entry, 0)?;
+            copy_segments(&image, &segments, &user_space)?;
+            Ok(image)
+        })?;
+    } else {
+        let image = KexecImage::new(&dests, entry, 0)?;
+        copy_segments(&image, &segments, &user_space)?;
+        load_image(image);
+    }
+
+    Ok(SyscallReturn::Return(0))
+}
+
+/// Checks whether the destinations are in the memory reserved for the crash kernel.
+fn check_crash_dests(dests: &[Range<usize>]) -> Result<()> {
+    let Some(region) = crash_kernel_region() else {
+        return_errno_with_message!(
+            Errno::EADDRNOTAVAIL,
+            "no memory is reserved for the crash kernel"
+        );
+    };
+    if dests
+        .iter()
+        .any(|dest| dest.start < region.start || region.end < dest.end)
+    {
+        return_errno_with_message!(
+            Errno::EADDRNOTAVAIL,
+            "the memory is not reserved for the crash kernel"
+        );
+    }
+
+    Ok(())
+}
+
+/// Copies the segments from the user space to the image.
+fn copy_segments(
+    image: &KexecImage,
+    segments: &[CKexecSegment],
+    user_space: &CurrentUserSpace,
+) -> Result<()> {
+    for (segment, c_segment) in image.segments().iter().zip(segments.iter()) {
+        let mut reader = user_space.reader(c_segment.buf, c_segment.bufsz)?;
+        reader.read_fallible(&mut segment.frames().writer())?;
+    }
+
+    Ok(())
+}
+
+pub fn sys_kexec_file_load(
+    kernel_fd: FileDesc,
+    initrd_fd: FileDesc,
+    cmdline_len: usize,
+    cmdline_addr: Vaddr,
+    flags: u64,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!(
+        "kernel_fd = {}, initrd_fd = {}, cmdline_len = {}, cmdline_addr = {:#x}, flags = {:#x}",
+        kernel_fd, initrd_fd, cmdline_len, cmdline_addr, flags
+    );
+
+    check_kexec_supported()?;
+    check_sys_boot(ctx)?;
+
+    let flags = KexecFileLoadFlags::from_bits(flags as u32)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "the flags are invalid"))?;
+    let on_crash = flags.contains(KexecFileLoadFlags::ON_CRASH);
+
+    if flags.contains(KexecFileLoadFlags::UNLOAD) {
+        if on_crash {
+            unload_crash_image();
+        } else {
+            unload_image();
+        }
+        return Ok(SyscallReturn::Return(0));
+    }
+
+    let (kernel_file, initrd_file) =
+        ctx.thread_local
+            .borrow_file_table_mut()
+            .read_with(|inner| {
+                let kernel_file = inner.get_file(kernel_fd)?.clone();
+                let initrd_file = if flags.contains(KexecFileLoadFlags::NO_INITRAMFS) {
+                    None
+                } else {
+                    Some(inner.get_file(initrd_fd)?.clone())
+                };
+                Ok::<_, Error>((kernel_file, initrd_file))
+            })?;
+
+    let cmdline = if cmdline_len == 0 {
+        vec![0]
+    } else {
+        let mut cmdline = vec![0; cmdline_len];
+        ctx.user_space().read_bytes(cmdline_addr, &mut cmdline)?;
+        if cmdline.last() != Some(&0) {
+            return_errno_with_message!(Errno::EINVAL, "the command line is not NUL-terminated");
+        }
+        cmdline
+    };
+
+    let kernel = read_whole_file(kernel_file.as_ref())?;
+    let initrd = initrd_file
+        .map(|file| read_whole_file(file.as_ref()))
+        .transpose()?;
+
+    if on_crash {
+        load_crash_image(|| load_crash_kernel_image(&kernel, initrd.as_deref(), &cmdline))?;
+    } else {
+        let image = load_kernel_image(&kernel, initrd.as_deref(), &cmdline)?;
+        load_image(image);
+    }
+
+    Ok(SyscallReturn::Return(0))
+}
+
+/// Checks whether the architecture supports kexec.
+///
+/// Jumping to a new kernel is only implemented on x86-64. On other architectures (i.e., RISC-V
+/// and LoongArch), the syscalls fail with `ENOSYS`, as if Linux were built without
+/// `CONFIG_KEXEC`.
+fn check_kexec_supported() -> Result<()> {
+    if !can_kexec() {
+        return_errno_with_message!(Errno::ENOSYS, "kexec is not supported on this platform");
+    }
+
+    Ok(())
+}
+
+fn check_sys_boot(ctx: &Context) -> Result<()> {
+    if !ctx
+        .posix_thread
+        .credentials()
+        .effective_capset()
+        .contains(CapSet::SYS_BOOT)
+    {
+        return_errno_with_message!(Errno::EPERM, "kexec without SYS_BOOT is not allowed");
+    }
+
+    Ok(())
+}
+
+/// Reads the whole content of a file.
+fn read_whole_file(file: &dyn FileLike) -> Result<Vec<u8>> {
+    let size = file.path().inode().metadata().size;
+    if size > MAX_FILE_SIZE {
+        return_errno_with_message!(Errno::EFBIG, "the file is too large");
+    }
+
+    let mut buf = vec![0; size];
+    let mut offset = 0;
+    while offset < size {
+        let len = file.read_bytes_at(offset, &mut buf[offset..])?;
+        if len == 0 {
+            break;
+        }
+        offset += len;
+    }
+    buf.truncate(offset);
+
+    Ok(buf)
+}
+
+const KEXEC_ARCH_MASK: u32 = 0xffff_0000;
+const KEXEC_ARCH_DEFAULT: u32 = 0;
+
+/// The maximum size of the kernel image or the initrd, which is the same as Linux's `INT_MAX`.
+const MAX_FILE_SIZE: usize = i32::MAX as usize;
+
+bitflags! {
+    struct KexecLoadFlags: u32 {
+        const ON_CRASH = 1 << 0;
+        const PRESERVE_CONTEXT = 1 << 1;
+    }
+}
+
+bitflags! {
+    struct KexecFileLoadFlags: u32 {
+        const UNLOAD = 1 << 0;
+        const ON_CRASH = 1 << 1;
+        const NO_INITRAMFS = 1 << 2;
+    }
+}
+
+/// A segment passed to `kexec_load`.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/include/uapi/linux/kexec.h#L56>
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CKexecSegment {
+    buf: Vaddr,
+    bufsz: usize,
+    mem: usize,
+    memsz: usize,
+}
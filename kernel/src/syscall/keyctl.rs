@@ -0,0 +1,610 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::format;
+use core::time::Duration;
+
+use ostd::mm::VmIo;
+
+use super::SyscallReturn;
+use crate::{
+    prelude::*,
+    process::{
+        Gid, Uid, UserNamespace, credentials::capabilities::CapSet, posix_thread::AsPosixThread,
+    },
+    security::keys::{
+        self, KEY_MAX_DESC_SIZE, KeyPerm, KeyPermMask, KeySerial, KeyType, ReqKeyDefault,
+    },
+};
+
+pub fn sys_add_key(
+    type_addr: Vaddr,
+    description_addr: Vaddr,
+    payload_addr: Vaddr,
+    payload_len: usize,
+    keyring: KeySerial,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    if payload_len > MAX_PAYLOAD_LEN {
+        return_errno_with_message!(Errno::EINVAL, "the payload is too long");
+    }
+
+    let type_name = read_type_name(type_addr, ctx)?;
+    let description = if description_addr == 0 {
+        None
+    } else {
+        Some(read_description(description_addr, ctx)?).filter(|desc| !desc.is_empty())
+    };
+    debug!(
+        "type = {}, description = {:?}, payload_len = {}, keyring = {}",
+        type_name, description, payload_len, keyring
+    );
+
+    if type_name == "keyring" && description.as_ref().is_some_and(|desc| desc.starts_with('.')) {
+        return_errno_with_message!(Errno::EPERM, "the keyring name is reserved");
+    }
+
+    let mut payload = vec![0; payload_len];
+    if payload_len > 0 {
+        ctx.user_space().read_bytes(payload_addr, &mut payload)?;
+    }
+
+    let keyring = keys::lookup_key_with_perm(ctx.posix_thread, keyring, true, KeyPerm::WRITE)?;
+
+    let Some(type_) = KeyType::from_name(&type_name) else {
+        return_errno_with_message!(Errno::ENODEV, "the key type is not supported");
+    };
+    let Some(description) = description else {
+        return_errno_with_message!(Errno::EINVAL, "the key description is empty");
+    };
+    let key = keys::add_key(ctx.posix_thread, type_, description, payload, &keyring)?;
+
+    Ok(SyscallReturn::Return(key.serial() as _))
+}
+
+pub fn sys_request_key(
+    type_addr: Vaddr,
+    description_addr: Vaddr,
+    callout_addr: Vaddr,
+    dest_keyring: KeySerial,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let type_name = read_type_name(type_addr, ctx)?;
+    let description = read_description(description_addr, ctx)?;
+    debug!(
+        "type = {}, description = {}, callout_addr = {:#x}, dest_keyring = {}",
+        type_name, description, callout_addr, dest_keyring
+    );
+
+    if callout_addr != 0 {
+        ctx.user_space().read_cstring(callout_addr, PAGE_SIZE)?;
+    }
+
+    let dest_keyring = if dest_keyring != 0 {
+        Some(keys::lookup_key_with_perm(ctx.posix_thread, dest_keyring, true, KeyPerm::WRITE)?)
+    } else {
+        None
+    };
+
+    let Some(type_) = KeyType::from_name(&type_name) else {
+        return_errno_with_message!(Errno::ENOKEY, "the key type is not supported");
+    };
+    // TODO: Support constructing the key by calling out to `/sbin/request-key` if the key cannot
+    // be found and the callout information is provided.
+    let key = keys::request_key(ctx.posix_thread, type_, &description)?;
+
+    if let Some(dest_keyring) = dest_keyring {
+        key.check_perm(ctx.posix_thread, KeyPerm::LINK)?;
+        dest_keyring.key().link(key.key().clone())?;
+    }
+
+    Ok(SyscallReturn::Return(key.key().serial() as _))
+}
+
+pub fn sys_keyctl(
+    option: i32,
+    arg2: u64,
+    arg3: u64,
+    arg4: u64,
+    arg5: u64,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let Ok(op) = KeyctlOp::try_from(option) else {
+        return_errno_with_message!(Errno::EOPNOTSUPP, "the keyctl operation is invalid");
+    };
+    debug!(
+        "op = {:?}, arg2 = {:#x}, arg3 = {:#x}, arg4 = {:#x}, arg5 = {:#x}",
+        op, arg2, arg3, arg4, arg5
+    );
+
+    // The key serial numbers are `int`s, so the upper bits of the arguments are ignored.
+    let posix_thread = ctx.posix_thread;
+    match op {
+        KeyctlOp::GetKeyringId => {
+            let key = keys::lookup_key_with_perm(
+                posix_thread,
+                arg2 as KeySerial,
+                arg3 != 0,
+                KeyPerm::SEARCH,
+            )?;
+            Ok(SyscallReturn::Return(key.key().serial() as _))
+        }
+        KeyctlOp::JoinSessionKeyring => {
+            let name = if arg2 == 0 {
+                None
+            } else {
+                Some(read_description(arg2 as Vaddr, ctx)?)
+            };
+            let serial = keys::join_session_keyring(posix_thread, name)?;
+            Ok(SyscallReturn::Return(serial as _))
+        }
+        KeyctlOp::Update => keyctl_update(arg2 as _, arg3 as _, arg4 as _, ctx),
+        KeyctlOp::Revoke => {
+            let key = keys::lookup_key(posix_thread, arg2 as KeySerial, false)?;
+            key.key().validate()?;
+            if key.check_perm(posix_thread, KeyPerm::WRITE).is_err() {
+                key.check_perm(posix_thread, KeyPerm::SETATTR)?;
+            }
+            key.key().revoke();
+            Ok(SyscallReturn::Return(0))
+        }
+        KeyctlOp::Chown => keyctl_chown(arg2 as _, arg3 as _, arg4 as _, ctx),
+        KeyctlOp::SetPerm => keyctl_setperm(arg2 as _, arg3 as _, ctx),
+        KeyctlOp::Describe => keyctl_describe(arg2 as _, arg3 as _, arg4 as _, ctx),
+        KeyctlOp::Clear => {
+            let keyring =
+                keys::lookup_key_with_perm(posix_thread, arg2 as KeySerial, true, KeyPerm::WRITE)?;
+            keyring.key().clear()?;
+            Ok(SyscallReturn::Return(0))
+        }
+        KeyctlOp::Link => {
+            let keyring =
+                keys::lookup_key_with_perm(posix_thread, arg3 as KeySerial, true, KeyPerm::WRITE)?;
+            let key =
+                keys::lookup_key_with_perm(posix_thread, arg2 as KeySerial, true, KeyPerm::LINK)?;
+            keyring.key().link(key.key().clone())?;
+            Ok(SyscallReturn::Return(0))
+        }
+        KeyctlOp::Unlink => {
+            let keyring =
+                keys::lookup_key_with_perm(posix_thread, arg3 as KeySerial, false, KeyPerm::WRITE)?;
+            // The key is not used, so its status and permissions are not checked.
+            let key = keys::lookup_key(posix_thread, arg2 as KeySerial, false)?;
+            keyring.key().unlink(key.key())?;
+            Ok(SyscallReturn::Return(0))
+        }
+        KeyctlOp::Search => keyctl_search(arg2 as _, arg3 as _, arg4 as _, arg5 as _, ctx),
+        KeyctlOp::Read => keyctl_read(arg2 as _, arg3 as _, arg4 as _, ctx),
+        KeyctlOp::Instantiate | KeyctlOp::Negate | KeyctlOp::Reject | KeyctlOp::InstantiateIov => {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "the thread is not authorized to instantiate the key"
+            );
+        }
+        KeyctlOp::SetReqKeyKeyring => {
+            let reqkey_default = arg2 as i32;
+            if reqkey_default == KEY_REQKEY_DEFL_NO_CHANGE {
+                let reqkey_default = posix_thread.keyrings().lock().reqkey_default();
+                return Ok(SyscallReturn::Return(reqkey_default as _));
+            }
+
+            let Ok(reqkey_default) = ReqKeyDefault::try_from(reqkey_default) else {
+                return_errno_with_message!(Errno::EINVAL, "the default keyring is invalid");
+            };
+            let old_reqkey_default = keys::set_reqkey_default(posix_thread, reqkey_default)?;
+            Ok(SyscallReturn::Return(old_reqkey_default as _))
+        }
+        KeyctlOp::SetTimeout => {
+            let key = keys::lookup_key_with_perm(
+                posix_thread,
+                arg2 as KeySerial,
+                true,
+                KeyPerm::SETATTR,
+            )?;
+            let timeout = arg3 as u32;
+            let timeout = (timeout != 0).then(|| Duration::from_secs(timeout as u64));
+            key.key().set_timeout(timeout);
+            Ok(SyscallReturn::Return(0))
+        }
+        KeyctlOp::AssumeAuthority => {
+            let serial = arg2 as KeySerial;
+            if serial < 0 {
+                return_errno_with_message!(Errno::EINVAL, "the key serial number is invalid");
+            }
+            if serial > 0 {
+                return_errno_with_message!(
+                    Errno::ENOKEY,
+                    "the key is not an authorization key for instantiation"
+                );
+            }
+            Ok(SyscallReturn::Return(0))
+        }
+        KeyctlOp::GetSecurity => {
+            keys::lookup_key_with_perm(posix_thread, arg2 as KeySerial, true, KeyPerm::VIEW)?;
+            // No security module labels keys, so the security label is an empty string.
+            write_to_user(arg3 as _, arg4 as _, b"\0", ctx)
+        }
+        KeyctlOp::SessionToParent => keyctl_session_to_parent(ctx),
+        KeyctlOp::Invalidate => {
+            let key = keys::lookup_key_with_perm(
+                posix_thread,
+                arg2 as KeySerial,
+                false,
+                KeyPerm::SEARCH,
+            )?;
+            key.key().invalidate();
+            Ok(SyscallReturn::Return(0))
+        }
+        KeyctlOp::Move => keyctl_move(arg2 as _, arg3 as _, arg4 as _, arg5 as _, ctx),
+        KeyctlOp::Capabilities => {
+            let caps = [
+                KEYCTL_CAPS0_CAPABILITIES | KEYCTL_CAPS0_INVALIDATE | KEYCTL_CAPS0_MOVE,
+                0,
+            ];
+            let len = (arg3 as usize).min(caps.len());
+            ctx.user_space().write_bytes(arg2 as _, &caps[..len])?;
+            Ok(SyscallReturn::Return(caps.len() as _))
+        }
+        KeyctlOp::GetPersistent
+        | KeyctlOp::DhCompute
+        | KeyctlOp::PkeyQuery
+        | KeyctlOp::PkeyEncrypt
+        | KeyctlOp::PkeyDecrypt
+        | KeyctlOp::PkeySign
+        | KeyctlOp::PkeyVerify
+        | KeyctlOp::RestrictKeyring
+        | KeyctlOp::WatchKey => {
+            return_errno_with_message!(Errno::EOPNOTSUPP, "the keyctl operation is not supported");
+        }
+    }
+}
+
+fn keyctl_update(
+    serial: KeySerial,
+    payload_addr: Vaddr,
+    payload_len: usize,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    if payload_len > PAGE_SIZE {
+        return_errno_with_message!(Errno::EINVAL, "the payload is too long");
+    }
+
+    let mut payload = vec![0; payload_len];
+    if payload_len > 0 {
+        ctx.user_space().read_bytes(payload_addr, &mut payload)?;
+    }
+
+    let key = keys::lookup_key_with_perm(ctx.posix_thread, serial, false, KeyPerm::WRITE)?;
+    key.key().update(payload)?;
+
+    Ok(SyscallReturn::Return(0))
+}
+
+fn keyctl_chown(serial: KeySerial, uid: u32, gid: u32, ctx: &Context) -> Result<SyscallReturn> {
+    let uid = (uid != u32::MAX).then(|| Uid::new(uid));
+    let gid = (gid != u32::MAX).then(|| Gid::new(gid));
+
+    let key = keys::lookup_key_with_perm(ctx.posix_thread, serial, true, KeyPerm::SETATTR)?;
+
+    // Reference: <https://elixir.bootlin.com/linux/v6.18/source/security/keys/keyctl.c#L968>
+    if !is_sys_admin(ctx) {
+        let attrs = key.key().attrs();
+        if uid.is_some_and(|uid| uid != attrs.uid) {
+            return_errno_with_message!(Errno::EACCES, "only administrators can change the owner");
+        }
+
+        let credentials = ctx.posix_thread.credentials();
+        let is_allowed_gid = |gid: Gid| {
+            gid == attrs.gid || gid == credentials.fsgid() || credentials.groups().contains(&gid)
+        };
+        if gid.is_some_and(|gid| !is_allowed_gid(gid)) {
+            return_errno_with_message!(Errno::EACCES, "the caller is not a member of the group");
+        }
+    }
+
+    key.key().set_owner(uid, gid);
+
+    Ok(SyscallReturn::Return(0))
+}
+
+fn keyctl_setperm(serial: KeySerial, perm: u32, ctx: &Context) -> Result<SyscallReturn> {
+    let perm = KeyPermMask::from_raw(perm)?;
+
+    let key = keys::lookup_key_with_perm(ctx.posix_thread, serial, true, KeyPerm::SETATTR)?;
+    if key.key().attrs().uid != ctx.posix_thread.credentials().fsuid() && !is_sys_admin(ctx) {
+        return_errno_with_message!(
+            Errno::EACCES,
+            "only the owner or the administrator can change the permissions"
+        );
+    }
+    key.key().set_perm(perm);
+
+    Ok(SyscallReturn::Return(0))
+}
+
+fn keyctl_describe(
+    serial: KeySerial,
+    buf_addr: Vaddr,
+    buf_len: usize,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let key = keys::lookup_key_with_perm(ctx.posix_thread, serial, true, KeyPerm::VIEW)?;
+
+    let attrs = key.key().attrs();
+    let uid = if attrs.uid == Uid::INVALID {
+        Uid::OVERFLOW
+    } else {
+        attrs.uid
+    };
+    let gid = if attrs.gid == Gid::INVALID {
+        Gid::OVERFLOW
+    } else {
+        attrs.gid
+    };
+    let description = format!(
+        "{};{};{};{:08x};{}\0",
+        key.key().type_(),
+        u32::from(uid),
+        u32::from(gid),
+        attrs.perm.as_raw(),
+        key.key().description()
+    );
+
+    write_to_user(buf_addr, buf_len, description.as_bytes(), ctx)
+}
+
+fn keyctl_search(
+    serial: KeySerial,
+    type_addr: Vaddr,
+    description_addr: Vaddr,
+    dest_keyring: KeySerial,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let type_name = read_type_name(type_addr, ctx)?;
+    let description = read_description(description_addr, ctx)?;
+
+    let keyring = keys::lookup_key_with_perm(ctx.posix_thread, serial, false, KeyPerm::SEARCH)?;
+    let dest_keyring = if dest_keyring != 0 {
+        Some(keys::lookup_key_with_perm(ctx.posix_thread, dest_keyring, true, KeyPerm::WRITE)?)
+    } else {
+        None
+    };
+
+    let Some(type_) = KeyType::from_name(&type_name) else {
+        return_errno_with_message!(Errno::ENOKEY, "the key type is not supported");
+    };
+    let key = keys::search_keyring(ctx.posix_thread, &keyring, type_, &description)?;
+
+    if let Some(dest_keyring) = dest_keyring {
+        key.check_perm(ctx.posix_thread, KeyPerm::LINK)?;
+        dest_keyring.key().link(key.key().clone())?;
+    }
+
+    Ok(SyscallReturn::Return(key.key().serial() as _))
+}
+
+fn keyctl_read(
+    serial: KeySerial,
+    buf_addr: Vaddr,
+    buf_len: usize,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let key = keys::lookup_key(ctx.posix_thread, serial, false)?;
+    key.key().validate()?;
+
+    // Like Linux, a key can be read if it is possessed, even if the read permission is not
+    // granted.
+    if !key.is_possessed() {
+        key.check_perm(ctx.posix_thread, KeyPerm::READ)?;
+    }
+
+    let payload = key.key().read()?;
+    if key.key().type_() == KeyType::Keyring
+        && buf_addr != 0
+        && buf_len % size_of::<KeySerial>() != 0
+    {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "the buffer length is not a multiple of the serial number size"
+        );
+    }
+
+    write_to_user(buf_addr, buf_len, &payload, ctx)
+}
+
+fn keyctl_session_to_parent(ctx: &Context) -> Result<SyscallReturn> {
+    let keyring = keys::lookup_key_with_perm(
+        ctx.posix_thread,
+        KEY_SPEC_SESSION_KEYRING,
+        false,
+        KeyPerm::LINK,
+    )?;
+
+    // Reference: <https://elixir.bootlin.com/linux/v6.18/source/security/keys/keyctl.c#L1610>
+    let Some(parent) = ctx.process.parent().lock().process().upgrade() else {
+        return_errno_with_message!(Errno::EPERM, "the process has no parent");
+    };
+    if parent.is_init_process() {
+        return_errno_with_message!(Errno::EPERM, "the parent is the init process");
+    }
+    if parent.tasks().lock().as_slice().len() != 1 {
+        return_errno_with_message!(Errno::EPERM, "the parent has multiple threads");
+    }
+
+    let parent_thread = parent.main_thread();
+    let parent_posix_thread = parent_thread.as_posix_thread().unwrap();
+    let credentials = ctx.posix_thread.credentials();
+    let parent_credentials = parent_posix_thread.credentials();
+    let euid = credentials.euid();
+    let egid = credentials.egid();
+    if parent_credentials.ruid() != euid
+        || parent_credentials.euid() != euid
+        || parent_credentials.suid() != euid
+        || parent_credentials.rgid() != egid
+        || parent_credentials.egid() != egid
+        || parent_credentials.sgid() != egid
+    {
+        return_errno_with_message!(Errno::EPERM, "the parent has different credentials");
+    }
+
+    keys::set_session_keyring(parent_posix_thread, keyring.key().clone());
+
+    Ok(SyscallReturn::Return(0))
+}
+
+fn keyctl_move(
+    serial: KeySerial,
+    from_keyring: KeySerial,
+    to_keyring: KeySerial,
+    flags: u32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    if flags & !KEYCTL_MOVE_EXCL != 0 {
+        return_errno_with_message!(Errno::EINVAL, "the flags are invalid");
+    }
+
+    let posix_thread = ctx.posix_thread;
+    let key = keys::lookup_key_with_perm(posix_thread, serial, true, KeyPerm::LINK)?;
+    let from_keyring =
+        keys::lookup_key_with_perm(posix_thread, from_keyring, false, KeyPerm::WRITE)?;
+    let to_keyring = keys::lookup_key_with_perm(posix_thread, to_keyring, true, KeyPerm::WRITE)?;
+    if Arc::ptr_eq(from_keyring.key(), to_keyring.key()) {
+        return Ok(SyscallReturn::Return(0));
+    }
+
+    if !from_keyring
+        .key()
+        .links()?
+        .iter()
+        .any(|link| Arc::ptr_eq(link, key.key()))
+    {
+        return_errno_with_message!(Errno::ENOENT, "the key is not linked to the keyring");
+    }
+    if flags & KEYCTL_MOVE_EXCL != 0
+        && to_keyring
+            .key()
+            .find_link(key.key().type_(), key.key().description())?
+            .is_some()
+    {
+        return_errno_with_message!(Errno::EEXIST, "the keyring has a key of the same name");
+    }
+
+    to_keyring.key().link(key.key().clone())?;
+    // The key may have been unlinked concurrently, which is not an error since it is moved.
+    let _ = from_keyring.key().unlink(key.key());
+
+    Ok(SyscallReturn::Return(0))
+}
+
+/// Reads the name of a key type from the user space.
+fn read_type_name(addr: Vaddr, ctx: &Context) -> Result<String> {
+    let type_name = ctx
+        .user_space()
+        .read_cstring(addr, KEY_TYPE_NAME_MAX_LEN)
+        .map_err(fix_too_long_error)?
+        .into_string()
+        .map_err(|_| Error::with_message(Errno::EINVAL, "the key type is not valid UTF-8"))?;
+
+    if type_name.is_empty() {
+        return_errno_with_message!(Errno::EINVAL, "the key type is empty");
+    }
+    if type_name.starts_with('.') {
+        return_errno_with_message!(Errno::EPERM, "the key type is internal");
+    }
+
+    Ok(type_name)
+}
+
+/// Reads a key description or a keyring name from the user space.
+fn read_description(addr: Vaddr, ctx: &Context) -> Result<String> {
+    ctx.user_space()
+        .read_cstring(addr, KEY_MAX_DESC_SIZE)
+        .map_err(fix_too_long_error)?
+        .into_string()
+        .map_err(|_| {
+            Error::with_message(Errno::EINVAL, "the key description is not valid UTF-8")
+        })
+}
+
+fn fix_too_long_error(err: Error) -> Error {
+    if err.error() == Errno::ENAMETOOLONG {
+        Error::with_message(Errno::EINVAL, "the string is too long")
+    } else {
+        err
+    }
+}
+
+/// Writes the bytes to the user buffer if the buffer is large enough.
+///
+/// Returns the length of the bytes, so that user space can retry with a larger buffer.
+fn write_to_user(
+    buf_addr: Vaddr,
+    buf_len: usize,
+    bytes: &[u8],
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    if buf_addr != 0 && buf_len >= bytes.len() {
+        ctx.user_space().write_bytes(buf_addr, bytes)?;
+    }
+
+    Ok(SyscallReturn::Return(bytes.len() as _))
+}
+
+fn is_sys_admin(ctx: &Context) -> bool {
+    UserNamespace::get_init_singleton()
+        .check_cap(CapSet::SYS_ADMIN, ctx.posix_thread)
+        .is_ok()
+}
+
+/// The maximum length of the payload of `add_key`.
+const MAX_PAYLOAD_LEN: usize = 1024 * 1024 - 1;
+
+/// The maximum length of a key type name, including the final nul byte.
+const KEY_TYPE_NAME_MAX_LEN: usize = 32;
+
+const KEY_SPEC_SESSION_KEYRING: KeySerial = -3;
+
+const KEY_REQKEY_DEFL_NO_CHANGE: i32 = -1;
+
+const KEYCTL_MOVE_EXCL: u32 = 0x01;
+
+const KEYCTL_CAPS0_CAPABILITIES: u8 = 0x01;
+const KEYCTL_CAPS0_INVALIDATE: u8 = 0x20;
+const KEYCTL_CAPS0_MOVE: u8 = 0x80;
+
+#[derive(Debug, Clone, Copy, TryFromInt)]
+#[repr(i32)]
+enum KeyctlOp {
+    GetKeyringId = 0,
+    JoinSessionKeyring = 1,
+    Update = 2,
+    Revoke = 3,
+    Chown = 4,
+    SetPerm = 5,
+    Describe = 6,
+    Clear = 7,
+    Link = 8,
+    Unlink = 9,
+    Search = 10,
+    Read = 11,
+    Instantiate = 12,
+    Negate = 13,
+    SetReqKeyKeyring = 14,
+    SetTimeout = 15,
+    AssumeAuthority = 16,
+    GetSecurity = 17,
+    SessionToParent = 18,
+    Reject = 19,
+    InstantiateIov = 20,
+    Invalidate = 21,
+    GetPersistent = 22,
+    DhCompute = 23,
+    PkeyQuery = 24,
+    PkeyEncrypt = 25,
+    PkeyDecrypt = 26,
+    PkeySign = 27,
+    PkeyVerify = 28,
+    RestrictKeyring = 29,
+    Move = 30,
+    Capabilities = 31,
+    WatchKey = 32,
+}
@@ -0,0 +1,190 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    fs::file::{
+        FileLike, InodeHandle,
+        file_table::{FdFlags, FileDesc, get_file_fast},
+    },
+    prelude::*,
+    process::credentials::capabilities::CapSet,
+    security::landlock::{self, FsAccess, LANDLOCK_ABI_VERSION, NetAccess, Ruleset, RulesetFile},
+    util::CopyCompat,
+};
+
+pub fn sys_landlock_create_ruleset(
+    attr_addr: Vaddr,
+    size: usize,
+    flags: u32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!("attr_addr = 0x{:x}, size = {}, flags = {:#x}", attr_addr, size, flags);
+
+    if flags != 0 {
+        if flags == LANDLOCK_CREATE_RULESET_VERSION && attr_addr == 0 && size == 0 {
+            return Ok(SyscallReturn::Return(LANDLOCK_ABI_VERSION as _));
+        }
+        return_errno_with_message!(Errno::EINVAL, "the flags are invalid");
+    }
+
+    if attr_addr == 0 {
+        return_errno_with_message!(Errno::EFAULT, "the attribute address is null");
+    }
+    if size < size_of::<u64>() {
+        return_errno_with_message!(Errno::EINVAL, "the attribute size is too small");
+    }
+    if size > PAGE_SIZE {
+        return_errno_with_message!(Errno::E2BIG, "the attribute size is too large");
+    }
+
+    let c_attr = ctx
+        .user_space()
+        .read_val_compat::<CLandlockRulesetAttr>(attr_addr, size)?;
+    debug!("ruleset_attr = {:?}", c_attr);
+
+    let handled_fs = FsAccess::from_bits(c_attr.handled_access_fs)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "the file access rights are invalid"))?;
+    let handled_net = NetAccess::from_bits(c_attr.handled_access_net)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "the port access rights are invalid"))?;
+    let ruleset_file = RulesetFile::new(Ruleset::new(handled_fs, handled_net)?);
+
+    let file_table = ctx.thread_local.borrow_file_table();
+    let fd = file_table
+        .unwrap()
+        .write()
+        .insert(Arc::new(ruleset_file), FdFlags::CLOEXEC);
+    Ok(SyscallReturn::Return(fd as _))
+}
+
+pub fn sys_landlock_add_rule(
+    ruleset_fd: FileDesc,
+    rule_type: u32,
+    rule_attr_addr: Vaddr,
+    flags: u32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!(
+        "ruleset_fd = {}, rule_type = {}, rule_attr_addr = 0x{:x}, flags = {:#x}",
+        ruleset_fd, rule_type, rule_attr_addr, flags
+    );
+
+    if flags != 0 {
+        return_errno_with_message!(Errno::EINVAL, "the flags are invalid");
+    }
+
+    let ruleset_file = get_ruleset_file(ruleset_fd, ctx)?;
+    let ruleset = ruleset_file
+        .downcast_ref::<RulesetFile>()
+        .unwrap()
+        .ruleset();
+
+    match rule_type {
+        LANDLOCK_RULE_PATH_BENEATH => {
+            let c_attr = ctx
+                .user_space()
+                .read_val::<CLandlockPathBeneathAttr>(rule_attr_addr)?;
+            let (allowed_access, parent_fd) = (c_attr.allowed_access, c_attr.parent_fd);
+            debug!("allowed_access = {:#x}, parent_fd = {}", allowed_access, parent_fd);
+
+            let access = FsAccess::from_bits(allowed_access).ok_or_else(|| {
+                Error::with_message(Errno::EINVAL, "the file access rights are invalid")
+            })?;
+            let path = {
+                let mut file_table = ctx.thread_local.borrow_file_table_mut();
+                let file = get_file_fast!(&mut file_table, parent_fd);
+                let Some(inode_handle) = file.downcast_ref::<InodeHandle>() else {
+                    return_errno_with_message!(Errno::EBADFD, "the file is not related to a path");
+                };
+                inode_handle.path().clone()
+            };
+            ruleset.add_path_rule(&path, access)?;
+        }
+        LANDLOCK_RULE_NET_PORT => {
+            let c_attr = ctx
+                .user_space()
+                .read_val::<CLandlockNetPortAttr>(rule_attr_addr)?;
+            debug!("net_port_attr = {:?}", c_attr);
+
+            let access = NetAccess::from_bits(c_attr.allowed_access).ok_or_else(|| {
+                Error::with_message(Errno::EINVAL, "the port access rights are invalid")
+            })?;
+            ruleset.add_port_rule(c_attr.port, access)?;
+        }
+        _ => return_errno_with_message!(Errno::EINVAL, "the rule type is invalid"),
+    }
+
+    Ok(SyscallReturn::Return(0))
+}
+
+pub fn sys_landlock_restrict_self(
+    ruleset_fd: FileDesc,
+    flags: u32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!("ruleset_fd = {}, flags = {:#x}", ruleset_fd, flags);
+
+    // Like `seccomp`, this requires `no_new_privs` or `CAP_SYS_ADMIN`, so that the restrictions
+    // cannot be used to confuse set-user-ID programs.
+    if !ctx.posix_thread.no_new_privs()
+        && ctx
+            .process
+            .user_ns()
+            .lock()
+            .check_cap(CapSet::SYS_ADMIN, ctx.posix_thread)
+            .is_err()
+    {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "`no_new_privs` or `CAP_SYS_ADMIN` is required to enforce a ruleset"
+        );
+    }
+
+    if flags != 0 {
+        return_errno_with_message!(Errno::EINVAL, "the flags are invalid");
+    }
+
+    let ruleset_file = get_ruleset_file(ruleset_fd, ctx)?;
+    let ruleset = ruleset_file
+        .downcast_ref::<RulesetFile>()
+        .unwrap()
+        .ruleset();
+    landlock::restrict_self(ctx.posix_thread, ruleset)?;
+
+    Ok(SyscallReturn::Return(0))
+}
+
+/// Gets the file of the ruleset, which is guaranteed to be a [`RulesetFile`].
+fn get_ruleset_file(fd: FileDesc, ctx: &Context) -> Result<Arc<dyn FileLike>> {
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+    let file = get_file_fast!(&mut file_table, fd);
+    if file.downcast_ref::<RulesetFile>().is_none() {
+        return_errno_with_message!(Errno::EBADFD, "the file is not a Landlock ruleset");
+    }
+    Ok(file.into_owned())
+}
+
+const LANDLOCK_CREATE_RULESET_VERSION: u32 = 1 << 0;
+
+const LANDLOCK_RULE_PATH_BENEATH: u32 = 1;
+const LANDLOCK_RULE_NET_PORT: u32 = 2;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CLandlockRulesetAttr {
+    handled_access_fs: u64,
+    handled_access_net: u64,
+}
+
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CLandlockPathBeneathAttr {
+    allowed_access: u64,
+    parent_fd: i32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CLandlockNetPortAttr {
+    allowed_access: u64,
+    port: u64,
+}
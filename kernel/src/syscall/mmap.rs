@@ -5,6 +5,7 @@ use align_ext::AlignExt;
 use super::SyscallReturn;
 use crate::{
     fs::file::file_table::{FileDesc, get_file_fast},
+    perf::PerfEventFile,
     prelude::*,
     vm::{
         perms::VmPerms,
@@ -122,6 +123,14 @@ fn do_sys_mmap(
                 vm_may_perms.remove(VmPerms::MAY_WRITE);
             }
 
+            // The ring buffer of a perf event is set up when the event file is mapped.
+            if let Some(perf_event_file) = file.downcast_ref::<PerfEventFile>() {
+                if !option.typ().is_shared() {
+                    return_errno_with_message!(Errno::EINVAL, "perf events must be mapped shared");
+                }
+                perf_event_file.setup_ring_buffer(len, offset)?;
+            }
+
             options = options
                 .may_perms(vm_may_perms)
                 .mappable(file.as_ref().as_ref())?
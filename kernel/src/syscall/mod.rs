@@ -11,7 +11,13 @@ pub use clock_gettime::ClockId;
 use ostd::arch::cpu::context::UserContext;
 pub use timer_create::create_timer;
 
-use crate::{context::Context, cpu::LinuxAbi, prelude::*};
+use crate::{
+    context::Context,
+    cpu::LinuxAbi,
+    prelude::*,
+    security::audit,
+    trace::events::raw_syscalls::{trace_sys_enter, trace_sys_exit},
+};
 
 #[cfg_attr(target_arch = "x86_64", path = "arch/x86.rs")]
 #[cfg_attr(target_arch = "riscv64", path = "arch/riscv.rs")]
@@ -20,9 +26,11 @@ mod arch;
 
 mod accept;
 mod access;
+mod adjtimex;
 mod alarm;
 mod arch_prctl;
 mod bind;
+mod bpf;
 mod brk;
 mod capget;
 mod capset;
@@ -75,7 +83,10 @@ mod getuid;
 mod getxattr;
 mod inotify;
 mod ioctl;
+mod kexec;
+mod keyctl;
 mod kill;
+mod landlock;
 mod link;
 mod listen;
 mod listxattr;
@@ -86,6 +97,7 @@ mod mkdir;
 mod mknod;
 mod mmap;
 mod mount;
+mod mount_setattr;
 mod mprotect;
 mod mremap;
 mod msync;
@@ -93,6 +105,7 @@ mod munmap;
 mod nanosleep;
 mod open;
 mod pause;
+mod perf_event_open;
 mod pidfd_getfd;
 mod pidfd_open;
 mod pidfd_send_signal;
@@ -107,10 +120,12 @@ mod prlimit64;
 mod pselect6;
 mod pwrite64;
 mod pwritev;
+mod quotactl;
 mod read;
 mod readlink;
 mod reboot;
 mod recvfrom;
+mod recvmmsg;
 mod recvmsg;
 mod removexattr;
 mod rename;
@@ -171,6 +186,7 @@ mod statx;
 mod symlink;
 mod sync;
 mod sysinfo;
+mod syslog;
 mod tgkill;
 mod time;
 mod timer_create;
@@ -327,7 +343,7 @@ macro_rules! impl_syscall_nums_and_dispatch_fn {
                     }
                 )*
                 _ => {
-                    log::warn!("Unimplemented syscall number: {}", syscall_number);
+                    aster_logger::warn_ratelimited!("Unimplemented syscall number: {}", syscall_number);
                     $crate::return_errno_with_message!($crate::error::Errno::ENOSYS, "Syscall was unimplemented");
                 }
             }
@@ -367,23 +383,30 @@ impl SyscallArgument {
 
 pub fn handle_syscall(ctx: &Context, user_ctx: &mut UserContext) {
     let syscall_frame = SyscallArgument::new_from_context(user_ctx);
-    let syscall_return = arch::syscall_dispatch(
-        syscall_frame.syscall_number,
-        syscall_frame.args,
-        ctx,
-        user_ctx,
-    );
+    let syscall_number = syscall_frame.syscall_number;
+    let [arg0, arg1, arg2, arg3, arg4, arg5] = syscall_frame.args;
+    trace_sys_enter(syscall_number, arg0, arg1, arg2, arg3, arg4, arg5);
+    audit::syscall_entry(ctx, syscall_number, &syscall_frame.args);
+
+    let syscall_return = arch::syscall_dispatch(syscall_number, syscall_frame.args, ctx, user_ctx);
 
     match syscall_return {
-        Ok(return_value) => {
-            if let SyscallReturn::Return(return_value) = return_value {
-                user_ctx.set_syscall_ret(return_value as usize);
-            }
+        Ok(SyscallReturn::Return(return_value)) => {
+            user_ctx.set_syscall_ret(return_value as usize);
+            trace_sys_exit(syscall_number, return_value as i64);
+            audit::syscall_exit(ctx, return_value as i64);
+        }
+        Ok(SyscallReturn::NoReturn) => {
+            // The system calls that do not return (e.g., `execve`) are audited as if they
+            // returned zero.
+            audit::syscall_exit(ctx, 0);
         }
         Err(err) => {
             debug!("syscall return error: {:?}", err);
             let errno = err.error() as i32;
-            user_ctx.set_syscall_ret((-errno) as usize)
+            user_ctx.set_syscall_ret((-errno) as usize);
+            trace_sys_exit(syscall_number, -errno as i64);
+            audit::syscall_exit(ctx, -errno as i64);
         }
     }
 }
@@ -152,13 +152,15 @@ fn do_change_type(target_path: Path, flags: MountFlags, ctx: &Context) -> Result
         );
     }
 
-    if flags.contains(MountFlags::MS_PRIVATE) {
-        let recursive = flags.contains(MountFlags::MS_REC);
-        target_path.set_mount_propagation(MountPropType::Private, recursive, ctx)?;
-        Ok(())
-    } else {
-        return_errno_with_message!(Errno::EINVAL, "the mount propagation type is unsupported");
-    }
+    let prop = match propagation_flags {
+        MountFlags::MS_SHARED => MountPropType::Shared,
+        MountFlags::MS_PRIVATE => MountPropType::Private,
+        MountFlags::MS_SLAVE => MountPropType::Slave,
+        MountFlags::MS_UNBINDABLE => MountPropType::Unbindable,
+        _ => unreachable!(),
+    };
+    let recursive = flags.contains(MountFlags::MS_REC);
+    target_path.set_mount_propagation(prop, recursive, ctx)
 }
 
 /// Moves a mount from src location to dst location.
@@ -267,7 +269,7 @@ fn open_fs(
         None
     };
 
-    fs_type.create(flags.into(), data, disk)
+    fs_type.create(flags.into(), dev_name, data, disk)
 }
 
 bitflags! {
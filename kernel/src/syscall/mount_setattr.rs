@@ -0,0 +1,243 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    fs::{
+        file::{InodeHandle, file_table::FileDesc},
+        pseudofs::NsFile,
+        utils::PATH_MAX,
+        vfs::path::{FsPath, MountAttrChange, MountIdmap, MountPropType, PerMountFlags},
+    },
+    prelude::*,
+    process::{UserNamespace, credentials::capabilities::CapSet},
+    util::CopyCompat,
+};
+
+pub fn sys_mount_setattr(
+    dirfd: FileDesc,
+    path_addr: Vaddr,
+    flags: u32,
+    attr_addr: Vaddr,
+    size: usize,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let flags = MountSetattrFlags::from_bits(flags)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid mount_setattr flags"))?;
+    let path_name = ctx.user_space().read_cstring(path_addr, PATH_MAX)?;
+    debug!(
+        "dirfd = {}, path_name = {:?}, flags = {:?}, attr_addr = 0x{:x}, size = {}",
+        dirfd, path_name, flags, attr_addr, size,
+    );
+
+    if size > PAGE_SIZE {
+        return_errno_with_message!(Errno::E2BIG, "the mount attribute size is too large");
+    }
+    if size < size_of::<MountAttr>() {
+        return_errno_with_message!(Errno::EINVAL, "the mount attribute size is too small");
+    }
+
+    ctx.thread_local
+        .borrow_user_ns()
+        .check_cap(CapSet::SYS_ADMIN, ctx.posix_thread)?;
+
+    let mount_attr = ctx
+        .user_space()
+        .read_val_compat::<MountAttr>(attr_addr, size)?;
+    debug!("mount_attr = {:?}", mount_attr);
+
+    // Nothing needs to be changed.
+    if mount_attr.attr_set == 0
+        && mount_attr.attr_clr == 0
+        && mount_attr.propagation == 0
+        && mount_attr.userns_fd == 0
+    {
+        return Ok(SyscallReturn::Return(0));
+    }
+
+    let attr_change = build_attr_change(&mount_attr, ctx)?;
+
+    let path = {
+        let path_name = path_name.to_string_lossy();
+        let fs_path = if flags.contains(MountSetattrFlags::AT_EMPTY_PATH) && path_name.is_empty() {
+            FsPath::from_fd(dirfd)?
+        } else {
+            FsPath::from_fd_and_path(dirfd, &path_name)?
+        };
+
+        let fs_ref = ctx.thread_local.borrow_fs();
+        let path_resolver = fs_ref.resolver().read();
+        if flags.contains(MountSetattrFlags::AT_SYMLINK_NOFOLLOW) {
+            path_resolver.lookup_no_follow(&fs_path)?
+        } else {
+            path_resolver.lookup(&fs_path)?
+        }
+    };
+
+    path.set_mount_attr(
+        &attr_change,
+        flags.contains(MountSetattrFlags::AT_RECURSIVE),
+        ctx,
+    )?;
+
+    Ok(SyscallReturn::Return(0))
+}
+
+/// Builds the change to the mount attributes from the user-provided `MountAttr`.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.16/source/fs/namespace.c#L4970>
+fn build_attr_change(mount_attr: &MountAttr, ctx: &Context) -> Result<MountAttrChange> {
+    let propagation = match mount_attr.propagation {
+        0 => None,
+        MS_SHARED => Some(MountPropType::Shared),
+        MS_PRIVATE => Some(MountPropType::Private),
+        MS_SLAVE => Some(MountPropType::Slave),
+        MS_UNBINDABLE => Some(MountPropType::Unbindable),
+        _ => return_errno_with_message!(Errno::EINVAL, "invalid mount propagation type"),
+    };
+
+    let attr_set = to_mount_attr_flags(mount_attr.attr_set)?;
+    let attr_clr = to_mount_attr_flags(mount_attr.attr_clr)?;
+
+    let mut flags_to_set = PerMountFlags::from(attr_set - MountAttrFlags::MOUNT_ATTR__ATIME);
+    let mut flags_to_clear = PerMountFlags::from(attr_clr - MountAttrFlags::MOUNT_ATTR__ATIME);
+
+    // The atime policy can only be changed by clearing all the atime flags first.
+    if attr_clr.contains(MountAttrFlags::MOUNT_ATTR__ATIME) {
+        let atime_flag = match attr_set & MountAttrFlags::MOUNT_ATTR__ATIME {
+            MountAttrFlags::MOUNT_ATTR_RELATIME => PerMountFlags::RELATIME,
+            MountAttrFlags::MOUNT_ATTR_NOATIME => PerMountFlags::NOATIME,
+            MountAttrFlags::MOUNT_ATTR_STRICTATIME => PerMountFlags::STRICTATIME,
+            _ => return_errno_with_message!(Errno::EINVAL, "invalid atime flags"),
+        };
+        flags_to_clear |= PerMountFlags::ATIME_MASK;
+        flags_to_set |= atime_flag;
+    } else if attr_set.intersects(MountAttrFlags::MOUNT_ATTR__ATIME) {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "the atime flags must be cleared before being set"
+        );
+    }
+
+    if attr_clr.contains(MountAttrFlags::MOUNT_ATTR_IDMAP) {
+        return_errno_with_message!(Errno::EINVAL, "the ID mapping of a mount cannot be cleared");
+    }
+    let idmap = if attr_set.contains(MountAttrFlags::MOUNT_ATTR_IDMAP) {
+        Some(Arc::new(build_idmap(mount_attr.userns_fd, ctx)?))
+    } else {
+        None
+    };
+
+    Ok(MountAttrChange {
+        flags_to_set,
+        flags_to_clear,
+        propagation,
+        idmap,
+    })
+}
+
+/// Builds the ID mapping of an idmapped mount from the file of a user namespace.
+fn build_idmap(userns_fd: u64, ctx: &Context) -> Result<MountIdmap> {
+    let userns_fd = FileDesc::try_from(userns_fd)
+        .map_err(|_| Error::with_message(Errno::EINVAL, "invalid user namespace fd"))?;
+
+    let file = {
+        let file_table = ctx.thread_local.borrow_file_table();
+        let file_table_locked = file_table.unwrap().read();
+        file_table_locked.get_file(userns_fd)?.clone()
+    };
+    let user_ns = file
+        .downcast_ref::<InodeHandle>()
+        .map(|inode_handle| inode_handle.downcast_file_io::<NsFile<UserNamespace>>())
+        .transpose()?
+        .flatten()
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "the fd is not a user namespace"))?
+        .ns()
+        .clone();
+
+    // Idmapped mounts with the initial user namespace are the same as normal mounts,
+    // so Linux rejects them.
+    if user_ns.is_init() {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "the initial user namespace cannot be used for idmapped mounts"
+        );
+    }
+    user_ns.check_cap(CapSet::SYS_ADMIN, ctx.posix_thread)?;
+
+    Ok(MountIdmap::new(user_ns))
+}
+
+fn to_mount_attr_flags(raw_flags: u64) -> Result<MountAttrFlags> {
+    u32::try_from(raw_flags)
+        .ok()
+        .and_then(MountAttrFlags::from_bits)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid mount attribute flags"))
+}
+
+impl From<MountAttrFlags> for PerMountFlags {
+    fn from(flags: MountAttrFlags) -> Self {
+        let mut per_mount_flags = PerMountFlags::empty();
+        for (attr_flag, per_mount_flag) in [
+            (MountAttrFlags::MOUNT_ATTR_RDONLY, PerMountFlags::RDONLY),
+            (MountAttrFlags::MOUNT_ATTR_NOSUID, PerMountFlags::NOSUID),
+            (MountAttrFlags::MOUNT_ATTR_NODEV, PerMountFlags::NODEV),
+            (MountAttrFlags::MOUNT_ATTR_NOEXEC, PerMountFlags::NOEXEC),
+            (
+                MountAttrFlags::MOUNT_ATTR_NODIRATIME,
+                PerMountFlags::NODIRATIME,
+            ),
+            (
+                MountAttrFlags::MOUNT_ATTR_NOSYMFOLLOW,
+                PerMountFlags::NOSYMFOLLOW,
+            ),
+        ] {
+            if flags.contains(attr_flag) {
+                per_mount_flags |= per_mount_flag;
+            }
+        }
+        per_mount_flags
+    }
+}
+
+// The propagation types accepted by `mount_setattr`.
+const MS_UNBINDABLE: u64 = 1 << 17;
+const MS_PRIVATE: u64 = 1 << 18;
+const MS_SLAVE: u64 = 1 << 19;
+const MS_SHARED: u64 = 1 << 20;
+
+/// The mount attributes passed to `mount_setattr`.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.16/source/include/uapi/linux/mount.h#L151>
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+struct MountAttr {
+    attr_set: u64,
+    attr_clr: u64,
+    propagation: u64,
+    userns_fd: u64,
+}
+
+bitflags! {
+    struct MountSetattrFlags: u32 {
+        const AT_SYMLINK_NOFOLLOW = 0x100;
+        const AT_NO_AUTOMOUNT     = 0x800;
+        const AT_EMPTY_PATH       = 0x1000;
+        const AT_RECURSIVE        = 0x8000;
+    }
+}
+
+bitflags! {
+    struct MountAttrFlags: u32 {
+        const MOUNT_ATTR_RDONLY      = 0x0000_0001;
+        const MOUNT_ATTR_NOSUID      = 0x0000_0002;
+        const MOUNT_ATTR_NODEV       = 0x0000_0004;
+        const MOUNT_ATTR_NOEXEC      = 0x0000_0008;
+        const MOUNT_ATTR__ATIME      = 0x0000_0070;
+        const MOUNT_ATTR_RELATIME    = 0x0000_0000;
+        const MOUNT_ATTR_NOATIME     = 0x0000_0010;
+        const MOUNT_ATTR_STRICTATIME = 0x0000_0020;
+        const MOUNT_ATTR_NODIRATIME  = 0x0000_0080;
+        const MOUNT_ATTR_IDMAP       = 0x0010_0000;
+        const MOUNT_ATTR_NOSYMFOLLOW = 0x0020_0000;
+    }
+}
@@ -4,12 +4,14 @@ use core::time::Duration;
 
 use ostd::{mm::VmIo, sync::Waiter};
 
-use super::{ClockId, SyscallReturn, clock_gettime::read_clock};
+use super::{
+    ClockId, SyscallReturn, clock_gettime::read_clock, timer_create::check_alarm_permission,
+};
 use crate::{
     prelude::*,
     time::{
         TIMER_ABSTIME, clockid_t,
-        clocks::{BootTimeClock, MonotonicClock, RealTimeClock},
+        clocks::{BootTimeClock, MonotonicClock, RealTimeClock, TaiClock},
         timer::Timeout,
         timespec_t,
         wait::ManagedTimeout,
@@ -75,27 +77,21 @@ fn do_clock_nanosleep(
         clockid, is_abs_time, request_time, remain_timespec_addr
     );
 
-    let start_time = read_clock(clockid, ctx)?;
-    let duration = if is_abs_time {
-        if request_time < start_time {
-            return Ok(SyscallReturn::Return(0));
-        }
-
-        request_time - start_time
-    } else {
-        request_time
-    };
-
-    // FIXME: sleeping thread can only be interrupted by signals that will call signal handler or terminate
-    // current process. i.e., the signals that should be ignored will not interrupt sleeping thread.
-    let waiter = Waiter::new_pair().0;
-
     let timer_manager = {
         let clock_id = ClockId::try_from(clockid)?;
         match clock_id {
             ClockId::CLOCK_BOOTTIME => BootTimeClock::timer_manager(),
             ClockId::CLOCK_MONOTONIC => MonotonicClock::timer_manager(),
             ClockId::CLOCK_REALTIME => RealTimeClock::timer_manager(),
+            ClockId::CLOCK_TAI => TaiClock::timer_manager(),
+            ClockId::CLOCK_REALTIME_ALARM => {
+                check_alarm_permission(ctx)?;
+                RealTimeClock::alarm_timer_manager()
+            }
+            ClockId::CLOCK_BOOTTIME_ALARM => {
+                check_alarm_permission(ctx)?;
+                BootTimeClock::alarm_timer_manager()
+            }
             // FIXME: We should better not expose this prof timer manager.
             ClockId::CLOCK_PROCESS_CPUTIME_ID => {
                 ctx.process.timer_manager().prof_timer().timer_manager()
@@ -108,6 +104,21 @@ fn do_clock_nanosleep(
         }
     };
 
+    let start_time = read_clock(clockid, ctx)?;
+    let duration = if is_abs_time {
+        if request_time < start_time {
+            return Ok(SyscallReturn::Return(0));
+        }
+
+        request_time - start_time
+    } else {
+        request_time
+    };
+
+    // FIXME: sleeping thread can only be interrupted by signals that will call signal handler or terminate
+    // current process. i.e., the signals that should be ignored will not interrupt sleeping thread.
+    let waiter = Waiter::new_pair().0;
+
     let res = waiter.pause_until_or_timeout(
         || None,
         ManagedTimeout::new_with_manager(Timeout::After(duration), timer_manager),
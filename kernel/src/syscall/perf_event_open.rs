@@ -0,0 +1,163 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::mem::offset_of;
+
+use ostd::{cpu::CpuId, mm::VmIo};
+
+use super::SyscallReturn;
+use crate::{
+    fs::file::file_table::{FdFlags, FileDesc, get_file_fast},
+    perf::{self, CPerfEventAttr, PERF_ATTR_SIZE_VER0, PerfEvent, PerfEventAttr, PerfEventFile},
+    prelude::*,
+    process::{
+        credentials::capabilities::CapSet,
+        posix_thread::{AsPosixThread, alien_access::AlienAccessMode, thread_table},
+    },
+    thread::Tid,
+    util::CopyCompat,
+};
+
+pub fn sys_perf_event_open(
+    attr_addr: Vaddr,
+    pid: i32,
+    cpu: i32,
+    group_fd: FileDesc,
+    flags: u32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!(
+        "attr_addr = 0x{:x}, pid = {}, cpu = {}, group_fd = {}, flags = {:#x}",
+        attr_addr, pid, cpu, group_fd, flags
+    );
+
+    let Some(flags) = PerfFlags::from_bits(flags) else {
+        return_errno_with_message!(Errno::EINVAL, "the flags are invalid");
+    };
+    if flags.intersects(PerfFlags::FD_OUTPUT | PerfFlags::PID_CGROUP) {
+        // TODO: Support redirecting the output and cgroup events.
+        return_errno_with_message!(Errno::EINVAL, "the flags are not supported");
+    }
+
+    let attr = read_attr(attr_addr, ctx)?;
+    debug!("attr = {:?}", attr);
+
+    let cpu = match cpu {
+        -1 => None,
+        cpu => {
+            let cpu = usize::try_from(cpu)
+                .ok()
+                .and_then(|cpu| CpuId::try_from(cpu).ok())
+                .ok_or_else(|| Error::with_message(Errno::EINVAL, "the CPU is invalid"))?;
+            Some(cpu)
+        }
+    };
+
+    let target_thread = match pid {
+        -1 => {
+            if cpu.is_none() {
+                return_errno_with_message!(
+                    Errno::EINVAL,
+                    "the event must be attached to a thread or a CPU"
+                );
+            }
+            // TODO: Allow unprivileged users to count CPU events if the
+            // `kernel.perf_event_paranoid` sysctl is not positive.
+            let effective_capset = ctx.posix_thread.credentials().effective_capset();
+            if !effective_capset.contains(CapSet::PERFMON)
+                && !effective_capset.contains(CapSet::SYS_ADMIN)
+            {
+                return_errno_with_message!(
+                    Errno::EACCES,
+                    "CPU events require CAP_PERFMON or CAP_SYS_ADMIN"
+                );
+            }
+            None
+        }
+        0 => Some(current_thread!()),
+        pid if pid > 0 => {
+            let Some(thread) = thread_table::get_thread(pid as Tid) else {
+                return_errno_with_message!(Errno::ESRCH, "the target thread does not exist");
+            };
+            thread
+                .as_posix_thread()
+                .unwrap()
+                .check_alien_access_from(ctx.posix_thread, AlienAccessMode::READ_WITH_REAL_CREDS)?;
+            Some(thread)
+        }
+        _ => return_errno_with_message!(Errno::EINVAL, "the PID is invalid"),
+    };
+
+    let leader = if group_fd == -1 || flags.contains(PerfFlags::FD_NO_GROUP) {
+        None
+    } else {
+        let mut file_table = ctx.thread_local.borrow_file_table_mut();
+        let file = get_file_fast!(&mut file_table, group_fd);
+        let Some(group_file) = file.downcast_ref::<PerfEventFile>() else {
+            return_errno_with_message!(Errno::EBADF, "the group file is not a perf event file");
+        };
+        let leader = group_file.event();
+        if !leader.is_leader() {
+            return_errno_with_message!(Errno::EINVAL, "the group file is not a group leader");
+        }
+        if leader.cpu() != cpu {
+            return_errno_with_message!(Errno::EINVAL, "the group leader is on a different CPU");
+        }
+        Some(leader.clone())
+    };
+
+    let event = PerfEvent::new(attr, cpu, target_thread.is_none(), leader);
+    match target_thread.as_ref() {
+        Some(thread) => thread
+            .as_posix_thread()
+            .unwrap()
+            .perf_events()
+            .attach(&event),
+        None => perf::attach_to_cpu(&event, cpu.unwrap()),
+    }
+
+    let fd_flags = if flags.contains(PerfFlags::FD_CLOEXEC) {
+        FdFlags::CLOEXEC
+    } else {
+        FdFlags::empty()
+    };
+    let file_table = ctx.thread_local.borrow_file_table();
+    let fd = file_table
+        .unwrap()
+        .write()
+        .insert(Arc::new(PerfEventFile::new(event)), fd_flags);
+    Ok(SyscallReturn::Return(fd as _))
+}
+
+fn read_attr(attr_addr: Vaddr, ctx: &Context) -> Result<PerfEventAttr> {
+    let user_space = ctx.user_space();
+    let size_addr = attr_addr + offset_of!(CPerfEventAttr, _size);
+
+    let size = match user_space.read_val::<u32>(size_addr)? {
+        0 => PERF_ATTR_SIZE_VER0,
+        size => size,
+    } as usize;
+    let c_attr = if size < PERF_ATTR_SIZE_VER0 as usize || size > PAGE_SIZE {
+        Err(Error::with_message(Errno::E2BIG, "the attribute size is invalid"))
+    } else {
+        user_space.read_val_compat::<CPerfEventAttr>(attr_addr, size)
+    };
+
+    match c_attr {
+        Ok(c_attr) => PerfEventAttr::try_from(&c_attr),
+        Err(err) if err.error() == Errno::E2BIG => {
+            // Like Linux, report the size that the kernel supports.
+            user_space.write_val(size_addr, &(size_of::<CPerfEventAttr>() as u32))?;
+            Err(err)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+bitflags! {
+    struct PerfFlags: u32 {
+        const FD_NO_GROUP = 1 << 0;
+        const FD_OUTPUT   = 1 << 1;
+        const PID_CGROUP  = 1 << 2;
+        const FD_CLOEXEC  = 1 << 3;
+    }
+}
@@ -78,6 +78,16 @@ pub fn sys_prctl(
             let mut thread_name = ctx.posix_thread.thread_name().lock();
             thread_name.set_name(&new_thread_name);
         }
+        PrctlCmd::PR_SET_NO_NEW_PRIVS(value) => {
+            if value != 1 {
+                return_errno_with_message!(Errno::EINVAL, "`no_new_privs` can only be set");
+            }
+            ctx.posix_thread.set_no_new_privs();
+        }
+        PrctlCmd::PR_GET_NO_NEW_PRIVS => {
+            let no_new_privs = ctx.posix_thread.no_new_privs();
+            return Ok(SyscallReturn::Return(no_new_privs as _));
+        }
         PrctlCmd::PR_SET_CHILD_SUBREAPER(is_set) => {
             let process = ctx.process.as_ref();
             if is_set {
@@ -135,6 +145,8 @@ const PR_SET_TIMERSLACK: i32 = 29;
 const PR_GET_TIMERSLACK: i32 = 30;
 const PR_SET_CHILD_SUBREAPER: i32 = 36;
 const PR_GET_CHILD_SUBREAPER: i32 = 37;
+const PR_SET_NO_NEW_PRIVS: i32 = 38;
+const PR_GET_NO_NEW_PRIVS: i32 = 39;
 
 #[expect(non_camel_case_types)]
 #[derive(Debug, Clone, Copy)]
@@ -151,6 +163,8 @@ pub enum PrctlCmd {
     PR_GET_DUMPABLE,
     PR_SET_CHILD_SUBREAPER(bool),
     PR_GET_CHILD_SUBREAPER(Vaddr),
+    PR_SET_NO_NEW_PRIVS(u64),
+    PR_GET_NO_NEW_PRIVS,
     PR_GET_SECUREBITS,
     PR_SET_SECUREBITS(SecureBits),
 }
@@ -181,6 +195,8 @@ impl PrctlCmd {
             PR_SET_KEEPCAPS => Ok(PrctlCmd::PR_SET_KEEPCAPS(arg2 as _)),
             PR_SET_CHILD_SUBREAPER => Ok(PrctlCmd::PR_SET_CHILD_SUBREAPER(arg2 > 0)),
             PR_GET_CHILD_SUBREAPER => Ok(PrctlCmd::PR_GET_CHILD_SUBREAPER(arg2 as _)),
+            PR_SET_NO_NEW_PRIVS => Ok(PrctlCmd::PR_SET_NO_NEW_PRIVS(arg2)),
+            PR_GET_NO_NEW_PRIVS => Ok(PrctlCmd::PR_GET_NO_NEW_PRIVS),
             PR_GET_SECUREBITS => Ok(PrctlCmd::PR_GET_SECUREBITS),
             PR_SET_SECUREBITS => Ok(PrctlCmd::PR_SET_SECUREBITS(SecureBits::try_from(
                 arg2 as u16,
@@ -0,0 +1,370 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    fs::{
+        file::{
+            InodeType,
+            file_table::{FileDesc, get_file_fast},
+        },
+        utils::PATH_MAX,
+        vfs::{
+            file_system::FileSystem,
+            inode::Inode,
+            path::{AT_FDCWD, FsPath},
+            quota::{DiskQuota, DiskQuotaUpdate, DiskQuotas, QuotaFormat, QuotaType},
+        },
+    },
+    prelude::*,
+    process::credentials::capabilities::CapSet,
+};
+
+pub fn sys_quotactl(
+    cmd: u32,
+    special_addr: Vaddr,
+    id: u32,
+    addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let (subcmd, type_) = decode_cmd(cmd);
+    debug!(
+        "subcmd = 0x{:x}, type = {}, special_addr = 0x{:x}, id = {}, addr = 0x{:x}",
+        subcmd, type_, special_addr, id, addr
+    );
+
+    // Syncing without a device syncs the quotas of all filesystems.
+    if subcmd == Q_SYNC && special_addr == 0 {
+        parse_type(type_)?;
+        let ns_proxy = ctx.thread_local.borrow_ns_proxy();
+        let mnt_ns = ns_proxy.unwrap().mnt_ns();
+        for fs in mnt_ns.filesystems() {
+            if let Some(quotas) = fs.disk_quotas() {
+                quotas.sync()?;
+            }
+        }
+        return Ok(SyscallReturn::Return(0));
+    }
+
+    let special = ctx.user_space().read_cstring(special_addr, PATH_MAX)?;
+    let path = {
+        let fs_path = FsPath::from_fd_and_path(AT_FDCWD, &special.to_string_lossy())?;
+        ctx.thread_local
+            .borrow_fs()
+            .resolver()
+            .read()
+            .lookup(&fs_path)?
+    };
+    if path.type_() != InodeType::BlockDevice {
+        return_errno_with_message!(Errno::ENOTBLK, "the path is not a block device");
+    }
+    let dev_id = path
+        .metadata()
+        .self_dev_id
+        .ok_or_else(|| Error::with_message(Errno::ENODEV, "the device is not found"))?;
+
+    let fs = {
+        let ns_proxy = ctx.thread_local.borrow_ns_proxy();
+        let mnt_ns = ns_proxy.unwrap().mnt_ns();
+        mnt_ns
+            .filesystems()
+            .into_iter()
+            .find(|fs| fs.sb().container_dev_id == dev_id)
+            .ok_or_else(|| {
+                Error::with_message(Errno::ENODEV, "no filesystem is mounted on the device")
+            })?
+    };
+
+    do_quotactl(&fs, subcmd, type_, id, addr, ctx)?;
+    Ok(SyscallReturn::Return(0))
+}
+
+pub fn sys_quotactl_fd(
+    fd: FileDesc,
+    cmd: u32,
+    id: u32,
+    addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let (subcmd, type_) = decode_cmd(cmd);
+    debug!(
+        "fd = {}, subcmd = 0x{:x}, type = {}, id = {}, addr = 0x{:x}",
+        fd, subcmd, type_, id, addr
+    );
+
+    let fs = {
+        let mut file_table = ctx.thread_local.borrow_file_table_mut();
+        let file = get_file_fast!(&mut file_table, fd);
+        file.path().fs()
+    };
+
+    do_quotactl(&fs, subcmd, type_, id, addr, ctx)?;
+    Ok(SyscallReturn::Return(0))
+}
+
+fn do_quotactl(
+    fs: &Arc<dyn FileSystem>,
+    subcmd: u32,
+    type_: u32,
+    id: u32,
+    addr: Vaddr,
+    ctx: &Context,
+) -> Result<()> {
+    let quotas = fs.disk_quotas().ok_or_else(|| {
+        Error::with_message(Errno::ENOSYS, "the filesystem does not support quotas")
+    })?;
+    let type_ = parse_type(type_)?;
+    check_permission(subcmd, type_, id, ctx)?;
+
+    let user_space = ctx.user_space();
+    match subcmd {
+        Q_SYNC => quotas.sync()?,
+        Q_QUOTAON => {
+            let format = QuotaFormat::try_from(id).map_err(|_| {
+                Error::with_message(Errno::ESRCH, "the quota format is unsupported")
+            })?;
+            let file = if quotas.has_hidden_files() {
+                None
+            } else {
+                Some(lookup_quota_file(fs, addr, ctx)?)
+            };
+            quotas.turn_on(type_, format, file)?;
+        }
+        Q_QUOTAOFF => quotas.turn_off(type_)?,
+        Q_GETFMT => {
+            let format = quotas.format(type_)?;
+            user_space.write_val(addr, &(format as u32))?;
+        }
+        Q_GETINFO => {
+            let info = quotas.info(type_)?;
+            let dqinfo = IfDqinfo {
+                dqi_bgrace: info.bgrace,
+                dqi_igrace: info.igrace,
+                dqi_flags: info.flags,
+                dqi_valid: IIF_ALL,
+            };
+            user_space.write_val(addr, &dqinfo)?;
+        }
+        Q_SETINFO => {
+            let dqinfo = user_space.read_val::<IfDqinfo>(addr)?;
+            set_info(quotas, type_, &dqinfo)?;
+        }
+        Q_GETQUOTA => {
+            let dquot = quotas.get_quota(type_, id)?;
+            user_space.write_val(addr, &IfDqblk::from(&dquot))?;
+        }
+        Q_SETQUOTA => {
+            let dqblk = user_space.read_val::<IfDqblk>(addr)?;
+            quotas.set_quota(type_, id, &DiskQuotaUpdate::from(&dqblk))?;
+        }
+        Q_GETNEXTQUOTA => {
+            let (next_id, dquot) = quotas.next_quota(type_, id)?;
+            let dqblk = IfDqblk::from(&dquot);
+            let next_dqblk = IfNextdqblk {
+                dqb_bhardlimit: dqblk.dqb_bhardlimit,
+                dqb_bsoftlimit: dqblk.dqb_bsoftlimit,
+                dqb_curspace: dqblk.dqb_curspace,
+                dqb_ihardlimit: dqblk.dqb_ihardlimit,
+                dqb_isoftlimit: dqblk.dqb_isoftlimit,
+                dqb_curinodes: dqblk.dqb_curinodes,
+                dqb_btime: dqblk.dqb_btime,
+                dqb_itime: dqblk.dqb_itime,
+                dqb_valid: dqblk.dqb_valid,
+                dqb_id: next_id,
+            };
+            user_space.write_val(addr, &next_dqblk)?;
+        }
+        _ => return_errno_with_message!(Errno::EINVAL, "the quotactl command is invalid"),
+    }
+
+    Ok(())
+}
+
+fn decode_cmd(cmd: u32) -> (u32, u32) {
+    (cmd >> SUBCMDSHIFT, cmd & SUBCMDMASK)
+}
+
+fn parse_type(type_: u32) -> Result<QuotaType> {
+    QuotaType::try_from(type_)
+        .map_err(|_| Error::with_message(Errno::EINVAL, "the quota type is invalid"))
+}
+
+/// Checks whether the current thread is allowed to perform the command.
+///
+/// Getting the information or the quotas of the user or groups of the thread is always
+/// allowed. Other commands that read the quotas of other IDs or modify the quotas
+/// require `CAP_SYS_ADMIN`.
+fn check_permission(subcmd: u32, type_: QuotaType, id: u32, ctx: &Context) -> Result<()> {
+    match subcmd {
+        Q_SYNC | Q_GETFMT | Q_GETINFO => return Ok(()),
+        Q_GETQUOTA | Q_GETNEXTQUOTA => {
+            let credentials = ctx.posix_thread.credentials();
+            let is_own_id = match type_ {
+                QuotaType::User => u32::from(credentials.euid()) == id,
+                QuotaType::Group => {
+                    u32::from(credentials.egid()) == id
+                        || credentials.groups().iter().any(|gid| u32::from(*gid) == id)
+                }
+                QuotaType::Project => false,
+            };
+            if is_own_id {
+                return Ok(());
+            }
+        }
+        _ => (),
+    }
+
+    ctx.thread_local
+        .borrow_user_ns()
+        .check_cap(CapSet::SYS_ADMIN, ctx.posix_thread)
+}
+
+/// Looks up the quota file specified on turning on quotas.
+fn lookup_quota_file(
+    fs: &Arc<dyn FileSystem>,
+    addr: Vaddr,
+    ctx: &Context,
+) -> Result<Arc<dyn Inode>> {
+    let name = ctx.user_space().read_cstring(addr, PATH_MAX)?;
+    let fs_path = FsPath::from_fd_and_path(AT_FDCWD, &name.to_string_lossy())?;
+    let path = ctx
+        .thread_local
+        .borrow_fs()
+        .resolver()
+        .read()
+        .lookup(&fs_path)?;
+    if !Arc::ptr_eq(&path.fs(), fs) {
+        return_errno_with_message!(Errno::EXDEV, "the quota file is not on the same filesystem");
+    }
+
+    Ok(path.inode().clone())
+}
+
+fn set_info(quotas: &DiskQuotas, type_: QuotaType, dqinfo: &IfDqinfo) -> Result<()> {
+    if dqinfo.dqi_valid & !IIF_ALL != 0 {
+        return_errno_with_message!(Errno::EINVAL, "the valid flags are invalid");
+    }
+    let flags = if dqinfo.dqi_valid & IIF_FLAGS != 0 {
+        if dqinfo.dqi_flags & !DQF_SETINFO_MASK != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the quota flags cannot be set");
+        }
+        Some(dqinfo.dqi_flags)
+    } else {
+        None
+    };
+
+    quotas.set_info(
+        type_,
+        (dqinfo.dqi_valid & IIF_BGRACE != 0).then_some(dqinfo.dqi_bgrace),
+        (dqinfo.dqi_valid & IIF_IGRACE != 0).then_some(dqinfo.dqi_igrace),
+        flags,
+    )
+}
+
+// Reference: <https://elixir.bootlin.com/linux/v6.16/source/include/uapi/linux/quota.h>
+
+const SUBCMDMASK: u32 = 0x00ff;
+const SUBCMDSHIFT: u32 = 8;
+
+const Q_SYNC: u32 = 0x800001;
+const Q_QUOTAON: u32 = 0x800002;
+const Q_QUOTAOFF: u32 = 0x800003;
+const Q_GETFMT: u32 = 0x800004;
+const Q_GETINFO: u32 = 0x800005;
+const Q_SETINFO: u32 = 0x800006;
+const Q_GETQUOTA: u32 = 0x800007;
+const Q_SETQUOTA: u32 = 0x800008;
+const Q_GETNEXTQUOTA: u32 = 0x800009;
+
+/// The size of the blocks in which the space limits are specified.
+const QIF_DQBLKSIZE: u64 = 1024;
+
+const QIF_BLIMITS: u32 = 1;
+const QIF_SPACE: u32 = 2;
+const QIF_ILIMITS: u32 = 4;
+const QIF_INODES: u32 = 8;
+const QIF_BTIME: u32 = 16;
+const QIF_ITIME: u32 = 32;
+const QIF_ALL: u32 = QIF_BLIMITS | QIF_SPACE | QIF_ILIMITS | QIF_INODES | QIF_BTIME | QIF_ITIME;
+
+const IIF_BGRACE: u32 = 1;
+const IIF_IGRACE: u32 = 2;
+const IIF_FLAGS: u32 = 4;
+const IIF_ALL: u32 = IIF_BGRACE | IIF_IGRACE | IIF_FLAGS;
+
+/// The flags that can be set with `Q_SETINFO`, which is `DQF_ROOT_SQUASH` only.
+const DQF_SETINFO_MASK: u32 = 1;
+
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+struct IfDqblk {
+    dqb_bhardlimit: u64,
+    dqb_bsoftlimit: u64,
+    dqb_curspace: u64,
+    dqb_ihardlimit: u64,
+    dqb_isoftlimit: u64,
+    dqb_curinodes: u64,
+    dqb_btime: u64,
+    dqb_itime: u64,
+    dqb_valid: u32,
+    _padding: u32,
+}
+
+impl From<&DiskQuota> for IfDqblk {
+    fn from(dquot: &DiskQuota) -> Self {
+        Self {
+            dqb_bhardlimit: dquot.bhardlimit / QIF_DQBLKSIZE,
+            dqb_bsoftlimit: dquot.bsoftlimit / QIF_DQBLKSIZE,
+            dqb_curspace: dquot.curspace,
+            dqb_ihardlimit: dquot.ihardlimit,
+            dqb_isoftlimit: dquot.isoftlimit,
+            dqb_curinodes: dquot.curinodes,
+            dqb_btime: dquot.btime,
+            dqb_itime: dquot.itime,
+            dqb_valid: QIF_ALL,
+            _padding: 0,
+        }
+    }
+}
+
+impl From<&IfDqblk> for DiskQuotaUpdate {
+    fn from(dqblk: &IfDqblk) -> Self {
+        let is_valid = |flag: u32| dqblk.dqb_valid & flag != 0;
+        Self {
+            blimits: is_valid(QIF_BLIMITS).then(|| {
+                (
+                    dqblk.dqb_bhardlimit.saturating_mul(QIF_DQBLKSIZE),
+                    dqblk.dqb_bsoftlimit.saturating_mul(QIF_DQBLKSIZE),
+                )
+            }),
+            curspace: is_valid(QIF_SPACE).then_some(dqblk.dqb_curspace),
+            ilimits: is_valid(QIF_ILIMITS).then_some((dqblk.dqb_ihardlimit, dqblk.dqb_isoftlimit)),
+            curinodes: is_valid(QIF_INODES).then_some(dqblk.dqb_curinodes),
+            btime: is_valid(QIF_BTIME).then_some(dqblk.dqb_btime),
+            itime: is_valid(QIF_ITIME).then_some(dqblk.dqb_itime),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+struct IfNextdqblk {
+    dqb_bhardlimit: u64,
+    dqb_bsoftlimit: u64,
+    dqb_curspace: u64,
+    dqb_ihardlimit: u64,
+    dqb_isoftlimit: u64,
+    dqb_curinodes: u64,
+    dqb_btime: u64,
+    dqb_itime: u64,
+    dqb_valid: u32,
+    dqb_id: u32,
+}
+
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+struct IfDqinfo {
+    dqi_bgrace: u64,
+    dqi_igrace: u64,
+    dqi_flags: u32,
+    dqi_valid: u32,
+}
@@ -19,6 +19,7 @@ enum RebootCmd {
     Restart = 0x01234567,
     Halt = 0xcdef0123,
     PowerOff = 0x4321fedc,
+    Kexec = 0x45584543,
     // TODO: Add more reboot sub-commands.
 }
 
@@ -61,5 +62,6 @@ pub fn sys_reboot(
     match cmd {
         RebootCmd::Restart => restart(ExitCode::Success),
         RebootCmd::Halt | RebootCmd::PowerOff => poweroff(ExitCode::Success),
+        RebootCmd::Kexec => match crate::power::kexec::kexec()? {},
     }
 }
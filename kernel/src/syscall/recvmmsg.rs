@@ -0,0 +1,122 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use ostd::mm::VmIo;
+
+use crate::{
+    fs::file::file_table::FileDesc,
+    net::socket::{Socket, util::SendRecvFlags},
+    prelude::*,
+    syscall::{
+        SyscallReturn,
+        recvmsg::{recv_one_message, write_message_header_to_user},
+    },
+    time::{clocks::MonotonicClock, timespec_t},
+    util::net::CUserMmsgHdr,
+};
+
+pub fn sys_recvmmsg(
+    sockfd: FileDesc,
+    mmsghdrs_addr: Vaddr,
+    count: u32,
+    flags: i32,
+    timeout_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let flags = SendRecvFlags::from_bits_truncate(flags);
+    let user_space = ctx.user_space();
+
+    let timeout = if timeout_addr != 0 {
+        let timeout = user_space.read_val::<timespec_t>(timeout_addr)?;
+        Some(Duration::try_from(timeout)?)
+    } else {
+        None
+    };
+
+    debug!(
+        "sockfd = {}, mmsghdrs = {:#x}, count = {}, flags = {:?}, timeout = {:?}",
+        sockfd, mmsghdrs_addr, count, flags, timeout
+    );
+
+    let file = {
+        // Writing control messages may access the file table,
+        // so we have to clone the file and drop the file table reference here.
+        let file_table = ctx.thread_local.borrow_file_table();
+        let file_table_locked = file_table.unwrap().read();
+        file_table_locked.get_file(sockfd)?.clone()
+    };
+    let socket = file.as_socket_or_err()?;
+
+    let deadline = timeout.map(|timeout| MonotonicClock::get().read_time() + timeout);
+
+    let mut received_msgs = 0;
+    let res = recv_mmsg_hdrs(
+        socket,
+        mmsghdrs_addr,
+        count as usize,
+        flags,
+        deadline,
+        &mut received_msgs,
+        ctx,
+    );
+    match res {
+        // Only return error if no packets are received successfully.
+        //
+        // FIXME: Linux records the error (except `EAGAIN`) in the socket, so that it can be
+        // reported by the next system call on the socket. We just discard the error.
+        Err(e) if received_msgs == 0 => return Err(e),
+        _ => (),
+    }
+
+    // Like Linux, the remaining time is written back only if some messages are received.
+    if let Some(deadline) = deadline {
+        let remaining = deadline.saturating_sub(MonotonicClock::get().read_time());
+        user_space.write_val(timeout_addr, &timespec_t::from(remaining))?;
+    }
+
+    Ok(SyscallReturn::Return(received_msgs as _))
+}
+
+fn recv_mmsg_hdrs(
+    socket: &dyn Socket,
+    mmsghdrs_addr: Vaddr,
+    count: usize,
+    mut flags: SendRecvFlags,
+    deadline: Option<Duration>,
+    received_msgs: &mut usize,
+    ctx: &Context,
+) -> Result<()> {
+    let user_space = ctx.user_space();
+
+    let wait_for_one = flags.contains(SendRecvFlags::MSG_WAITFORONE);
+    flags.remove(SendRecvFlags::MSG_WAITFORONE);
+
+    for i in 0..count.min(CUserMmsgHdr::MAX_COUNT) {
+        let addr = mmsghdrs_addr + size_of::<CUserMmsgHdr>() * i;
+        let mut mmsghdr = user_space.read_val::<CUserMmsgHdr>(addr)?;
+
+        let (received_bytes, message_header) =
+            recv_one_message(socket, &mmsghdr.msg_hdr, &user_space, flags)?;
+        write_message_header_to_user(&mut mmsghdr.msg_hdr, &message_header, &user_space)?;
+
+        mmsghdr.msg_len = received_bytes as u32;
+        user_space.write_val(addr, &mmsghdr)?;
+
+        *received_msgs += 1;
+
+        // After the first message is received, `MSG_WAITFORONE` makes the remaining receptions
+        // nonblocking.
+        if wait_for_one {
+            flags.insert(SendRecvFlags::MSG_DONTWAIT);
+        }
+
+        // Like Linux, the timeout is only checked after a message is received, so the call may
+        // still block indefinitely if no messages arrive.
+        if deadline.is_some_and(|deadline| MonotonicClock::get().read_time() >= deadline) {
+            break;
+        }
+    }
+
+    Ok(())
+}
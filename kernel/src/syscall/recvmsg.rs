@@ -5,7 +5,10 @@ use ostd::mm::VmIo;
 use super::SyscallReturn;
 use crate::{
     fs::file::file_table::{FileDesc, get_file_fast},
-    net::socket::util::SendRecvFlags,
+    net::socket::{
+        Socket,
+        util::{MessageHeader, SendRecvFlags},
+    },
     prelude::*,
     util::net::CUserMsgHdr,
 };
@@ -29,29 +32,50 @@ pub fn sys_recvmsg(
     let file = get_file_fast!(&mut file_table, sockfd);
     let socket = file.as_socket_or_err()?;
 
-    let (total_bytes, message_header) = {
-        let mut io_vec_writer = c_user_msghdr.copy_writer_array_from_user(&user_space)?;
-        socket
-            .recvmsg(&mut io_vec_writer, flags)
-            .map_err(|err| match err.error() {
-                // FIXME: `recvmsg` should not be restarted if a timeout has been set on the socket using `setsockopt`.
-                Errno::EINTR => Error::new(Errno::ERESTARTSYS),
-                _ => err,
-            })?
-    };
+    let (total_bytes, message_header) =
+        recv_one_message(socket, &c_user_msghdr, &user_space, flags)?;
 
     // Writing control messages may access the file table, so it should be called after dropping
     // the file table borrow.
     drop(file_table);
 
+    write_message_header_to_user(&mut c_user_msghdr, &message_header, &user_space)?;
+    user_space.write_val(user_msghdr_ptr, &c_user_msghdr)?;
+
+    Ok(SyscallReturn::Return(total_bytes as _))
+}
+
+pub(super) fn recv_one_message(
+    socket: &dyn Socket,
+    c_user_msghdr: &CUserMsgHdr,
+    user_space: &CurrentUserSpace,
+    flags: SendRecvFlags,
+) -> Result<(usize, MessageHeader)> {
+    let mut io_vec_writer = c_user_msghdr.copy_writer_array_from_user(user_space)?;
+
+    socket
+        .recvmsg(&mut io_vec_writer, flags)
+        .map_err(|err| match err.error() {
+            // FIXME: `recvmsg` should not be restarted if a timeout has been set on the socket using `setsockopt`.
+            Errno::EINTR => Error::new(Errno::ERESTARTSYS),
+            _ => err,
+        })
+}
+
+/// Writes the socket address and the control messages of a received message to the user space.
+///
+/// Note that this function may access the file table when writing control messages.
+pub(super) fn write_message_header_to_user(
+    c_user_msghdr: &mut CUserMsgHdr,
+    message_header: &MessageHeader,
+    user_space: &CurrentUserSpace,
+) -> Result<()> {
     let addr = message_header.addr();
     c_user_msghdr.msg_namelen = c_user_msghdr.write_socket_addr_to_user(addr)?;
 
     let control_messages = message_header.control_messages();
     c_user_msghdr.msg_controllen =
-        c_user_msghdr.write_control_messages_to_user(control_messages, &user_space)? as _;
+        c_user_msghdr.write_control_messages_to_user(control_messages, user_space)? as _;
 
-    user_space.write_val(user_msghdr_ptr, &c_user_msghdr)?;
-
-    Ok(SyscallReturn::Return(total_bytes as _))
+    Ok(())
 }
@@ -9,7 +9,13 @@ use ostd::{
 };
 
 use super::SyscallReturn;
-use crate::{prelude::*, process::posix_thread::thread_table, thread::Tid};
+use crate::{
+    cpu::hotplug::{is_cpu_online, online_cpus_in},
+    prelude::*,
+    process::posix_thread::thread_table,
+    sched::set_cpu_affinity,
+    thread::Tid,
+};
 
 pub fn sys_sched_getaffinity(
     tid: Tid,
@@ -24,16 +30,15 @@ pub fn sys_sched_getaffinity(
             None => return Err(Error::with_message(Errno::ESRCH, "thread does not exist")),
         },
     };
+    // Like Linux, only the online CPUs are reported. The affinity is broken if all the allowed
+    // CPUs are offline, so all the online CPUs are reported in that case.
+    let cpu_set = online_cpus_in(&cpu_set);
 
     let bytes_written = write_cpu_set_to(ctx.user_space(), &cpu_set, cpuset_size, cpu_set_ptr)?;
 
     Ok(SyscallReturn::Return(bytes_written as isize))
 }
 
-// TODO: The manual page of `sched_setaffinity` says that if the thread is not
-// running on the CPU specified in the affinity mask, it would be migrated to
-// one of the CPUs specified in the mask. We currently do not support this
-// feature as the scheduler is not ready for migration yet.
 pub fn sys_sched_setaffinity(
     tid: Tid,
     cpuset_size: usize,
@@ -41,18 +46,18 @@ pub fn sys_sched_setaffinity(
     ctx: &Context,
 ) -> Result<SyscallReturn> {
     let user_cpu_set = read_cpu_set_from(ctx.user_space(), cpuset_size, cpu_set_ptr)?;
+    if !user_cpu_set.iter().any(is_cpu_online) {
+        return Err(Error::with_message(
+            Errno::EINVAL,
+            "no online CPUs in cpuset",
+        ));
+    }
 
+    // If the thread is not running on the CPUs in the new mask, it is migrated to one of them.
     match tid {
-        0 => ctx
-            .thread
-            .atomic_cpu_affinity()
-            .store(&user_cpu_set, Ordering::Relaxed),
+        0 => set_cpu_affinity(ctx.thread, &user_cpu_set),
         _ => match thread_table::get_thread(tid) {
-            Some(thread) => {
-                thread
-                    .atomic_cpu_affinity()
-                    .store(&user_cpu_set, Ordering::Relaxed);
-            }
+            Some(thread) => set_cpu_affinity(&thread, &user_cpu_set),
             None => return Err(Error::with_message(Errno::ESRCH, "thread does not exist")),
         },
     }
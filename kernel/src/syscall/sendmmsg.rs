@@ -7,7 +7,7 @@ use crate::{
     net::socket::{Socket, util::SendRecvFlags},
     prelude::*,
     syscall::{SyscallReturn, sendmsg::send_one_message},
-    util::net::CUserMsgHdr,
+    util::net::CUserMmsgHdr,
 };
 
 pub fn sys_sendmmsg(
@@ -46,14 +46,6 @@ pub fn sys_sendmmsg(
     }
 }
 
-#[repr(C)]
-#[padding_struct]
-#[derive(Debug, Clone, Copy, Pod)]
-struct CMmsgHdr {
-    msg_hdr: CUserMsgHdr,
-    msg_len: u32,
-}
-
 fn send_mmsg_hdrs(
     socket: &dyn Socket,
     mmsghdrs_addr: Vaddr,
@@ -64,9 +56,9 @@ fn send_mmsg_hdrs(
 ) -> Result<()> {
     let user_space = ctx.user_space();
 
-    for i in 0..count {
-        let addr = mmsghdrs_addr + size_of::<CMmsgHdr>() * i;
-        let mut mmsghdr = user_space.read_val::<CMmsgHdr>(addr)?;
+    for i in 0..count.min(CUserMmsgHdr::MAX_COUNT) {
+        let addr = mmsghdrs_addr + size_of::<CUserMmsgHdr>() * i;
+        let mut mmsghdr = user_space.read_val::<CUserMmsgHdr>(addr)?;
 
         let sent_bytes = send_one_message(socket, &mmsghdr.msg_hdr, &user_space, flags)?;
 
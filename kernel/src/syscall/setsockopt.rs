@@ -3,6 +3,7 @@
 use super::SyscallReturn;
 use crate::{
     fs::file::file_table::{FileDesc, get_file_fast},
+    net::{netfilter, socket::util::SocketAddr},
     prelude::*,
     util::net::{CSocketOptionLevel, new_raw_socket_option},
 };
@@ -16,7 +17,9 @@ pub fn sys_setsockopt(
     ctx: &Context,
 ) -> Result<SyscallReturn> {
     let level = CSocketOptionLevel::try_from(level).map_err(|_| Errno::EOPNOTSUPP)?;
-    if optval == 0 {
+    // `ALG_SET_AEAD_AUTHSIZE` passes its value as the option length, where the option value can
+    // be a null pointer.
+    if optval == 0 && level != CSocketOptionLevel::SOL_ALG {
         return_errno_with_message!(Errno::EINVAL, "optval is null pointer");
     }
 
@@ -29,6 +32,16 @@ pub fn sys_setsockopt(
     let file = get_file_fast!(&mut file_table, sockfd);
     let socket = file.as_socket_or_err()?;
 
+    // The iptables options share the same names between `getsockopt` and `setsockopt`, so they
+    // are handled separately.
+    if level == CSocketOptionLevel::SOL_IP
+        && netfilter::is_iptables_option(optname)
+        && matches!(socket.addr(), Ok(SocketAddr::IPv4(..)))
+    {
+        netfilter::set_iptables_option(optname, optval, optlen)?;
+        return Ok(SyscallReturn::Return(0));
+    }
+
     let raw_option = {
         let mut option = new_raw_socket_option(level, optname)?;
         option.read_from_user(optval, optlen)?;
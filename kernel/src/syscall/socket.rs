@@ -4,14 +4,18 @@ use super::SyscallReturn;
 use crate::{
     fs::file::{FileLike, file_table::FdFlags},
     net::socket::{
-        ip::{DatagramSocket, StreamSocket},
+        alg::AlgSocket,
+        ip::{DatagramSocket, IpFamily, RawSocket, StreamSocket},
         netlink::{
-            NetlinkRouteSocket, NetlinkUeventSocket, StandardNetlinkProtocol, is_valid_protocol,
+            NetlinkAuditSocket, NetlinkGenericSocket, NetlinkRouteSocket, NetlinkUeventSocket,
+            StandardNetlinkProtocol, is_valid_protocol,
         },
+        packet::PacketSocket,
         unix::{UnixDatagramSocket, UnixStreamSocket},
         vsock::VsockStreamSocket,
     },
     prelude::*,
+    security::lsm,
     util::net::{CSocketAddrFamily, Protocol, SOCK_TYPE_MASK, SockFlags, SockType},
 };
 
@@ -23,6 +27,7 @@ pub fn sys_socket(domain: i32, type_: i32, protocol: i32, ctx: &Context) -> Resu
         "domain = {:?}, sock_type = {:?}, sock_flags = {:?}",
         domain, sock_type, sock_flags
     );
+    lsm::socket_create(domain, sock_type, protocol)?;
 
     let is_nonblocking = sock_flags.contains(SockFlags::SOCK_NONBLOCK);
     let file_like = match (domain, sock_type) {
@@ -35,26 +40,56 @@ pub fn sys_socket(domain: i32, type_: i32, protocol: i32, ctx: &Context) -> Resu
         (CSocketAddrFamily::AF_UNIX, SockType::SOCK_RAW | SockType::SOCK_DGRAM) => {
             UnixDatagramSocket::new(is_nonblocking) as Arc<dyn FileLike>
         }
-        (CSocketAddrFamily::AF_INET, SockType::SOCK_STREAM) => {
+        (CSocketAddrFamily::AF_INET | CSocketAddrFamily::AF_INET6, SockType::SOCK_STREAM) => {
+            let family = ip_family_of(domain);
             let protocol = Protocol::try_from(protocol)?;
             debug!("protocol = {:?}", protocol);
             match protocol {
                 Protocol::IPPROTO_IP | Protocol::IPPROTO_TCP => {
-                    StreamSocket::new(is_nonblocking) as Arc<dyn FileLike>
+                    StreamSocket::new(is_nonblocking, family) as Arc<dyn FileLike>
                 }
                 _ => return_errno_with_message!(Errno::EAFNOSUPPORT, "unsupported protocol"),
             }
         }
-        (CSocketAddrFamily::AF_INET, SockType::SOCK_DGRAM) => {
+        (CSocketAddrFamily::AF_INET | CSocketAddrFamily::AF_INET6, SockType::SOCK_DGRAM) => {
+            let family = ip_family_of(domain);
             let protocol = Protocol::try_from(protocol)?;
             debug!("protocol = {:?}", protocol);
             match protocol {
                 Protocol::IPPROTO_IP | Protocol::IPPROTO_UDP => {
-                    DatagramSocket::new(is_nonblocking) as Arc<dyn FileLike>
+                    DatagramSocket::new(is_nonblocking, family) as Arc<dyn FileLike>
+                }
+                // TODO: Support ICMPv6 ping sockets.
+                Protocol::IPPROTO_ICMP if family == IpFamily::Ipv4 => {
+                    RawSocket::new_ping(is_nonblocking)? as Arc<dyn FileLike>
                 }
                 _ => return_errno_with_message!(Errno::EAFNOSUPPORT, "unsupported protocol"),
             }
         }
+        // TODO: Support IPv6 raw sockets.
+        (CSocketAddrFamily::AF_INET, SockType::SOCK_RAW) => {
+            debug!("protocol = {}", protocol);
+            // Like Linux, any protocol number except zero can be used by raw sockets.
+            let protocol = match u8::try_from(protocol) {
+                Ok(0) => {
+                    return_errno_with_message!(Errno::EPROTONOSUPPORT, "unsupported protocol")
+                }
+                Ok(protocol) => protocol,
+                Err(_) => return_errno_with_message!(Errno::EINVAL, "invalid protocol"),
+            };
+            RawSocket::new_raw(is_nonblocking, protocol)? as Arc<dyn FileLike>
+        }
+        (CSocketAddrFamily::AF_PACKET, SockType::SOCK_RAW | SockType::SOCK_DGRAM) => {
+            // Like Linux, the protocol is in network byte order and its higher bits are ignored.
+            let protocol = u16::from_be(protocol as u16);
+            debug!("protocol = {:#06x}", protocol);
+            match sock_type {
+                SockType::SOCK_RAW => {
+                    PacketSocket::new_raw(is_nonblocking, protocol)? as Arc<dyn FileLike>
+                }
+                _ => PacketSocket::new_dgram(is_nonblocking, protocol)? as Arc<dyn FileLike>,
+            }
+        }
         (CSocketAddrFamily::AF_NETLINK, SockType::SOCK_RAW | SockType::SOCK_DGRAM) => {
             let netlink_family = StandardNetlinkProtocol::try_from(protocol as u32);
             debug!("netlink family = {:?}", netlink_family);
@@ -65,6 +100,12 @@ pub fn sys_socket(domain: i32, type_: i32, protocol: i32, ctx: &Context) -> Resu
                 Ok(StandardNetlinkProtocol::KOBJECT_UEVENT) => {
                     NetlinkUeventSocket::new(is_nonblocking) as Arc<dyn FileLike>
                 }
+                Ok(StandardNetlinkProtocol::GENERIC) => {
+                    NetlinkGenericSocket::new(is_nonblocking) as Arc<dyn FileLike>
+                }
+                Ok(StandardNetlinkProtocol::AUDIT) => {
+                    NetlinkAuditSocket::new(is_nonblocking) as Arc<dyn FileLike>
+                }
                 Ok(_) => {
                     return_errno_with_message!(
                         Errno::EAFNOSUPPORT,
@@ -85,6 +126,26 @@ pub fn sys_socket(domain: i32, type_: i32, protocol: i32, ctx: &Context) -> Resu
         (CSocketAddrFamily::AF_VSOCK, SockType::SOCK_STREAM) => {
             Arc::new(VsockStreamSocket::new(is_nonblocking)?) as Arc<dyn FileLike>
         }
+        // Like the virtio transport in Linux, the virtio-vsock device does not support datagram
+        // sockets, since the virtio-vsock specification defines no datagram packets.
+        (CSocketAddrFamily::AF_VSOCK, SockType::SOCK_DGRAM) => {
+            return_errno_with_message!(
+                Errno::ENODEV,
+                "no vsock transport supports datagram sockets"
+            );
+        }
+        (CSocketAddrFamily::AF_VSOCK, _) => {
+            return_errno_with_message!(Errno::ESOCKTNOSUPPORT, "unsupported vsock socket type");
+        }
+        (CSocketAddrFamily::AF_ALG, SockType::SOCK_SEQPACKET) => {
+            if protocol != 0 {
+                return_errno_with_message!(Errno::EPROTONOSUPPORT, "invalid alg protocol");
+            }
+            AlgSocket::new(is_nonblocking) as Arc<dyn FileLike>
+        }
+        (CSocketAddrFamily::AF_ALG, _) => {
+            return_errno_with_message!(Errno::ESOCKTNOSUPPORT, "unsupported alg socket type");
+        }
         _ => return_errno_with_message!(Errno::EAFNOSUPPORT, "unsupported domain"),
     };
 
@@ -101,3 +162,10 @@ pub fn sys_socket(domain: i32, type_: i32, protocol: i32, ctx: &Context) -> Resu
 
     Ok(SyscallReturn::Return(fd as _))
 }
+
+fn ip_family_of(domain: CSocketAddrFamily) -> IpFamily {
+    match domain {
+        CSocketAddrFamily::AF_INET6 => IpFamily::Ipv6,
+        _ => IpFamily::Ipv4,
+    }
+}
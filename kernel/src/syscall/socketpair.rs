@@ -10,6 +10,7 @@ use crate::{
     },
     net::socket::unix::{UnixDatagramSocket, UnixStreamSocket},
     prelude::*,
+    security::lsm,
     util::net::{CSocketAddrFamily, Protocol, SOCK_TYPE_MASK, SockFlags, SockType},
 };
 
@@ -23,6 +24,7 @@ pub fn sys_socketpair(
     let domain = CSocketAddrFamily::try_from(domain)?;
     let sock_type = SockType::try_from(type_ & SOCK_TYPE_MASK)?;
     let sock_flags = SockFlags::from_bits_truncate(type_ & !SOCK_TYPE_MASK);
+    lsm::socket_create(domain, sock_type, protocol)?;
     let protocol = Protocol::try_from(protocol)?;
     debug!(
         "domain = {:?}, sock_type = {:?}, sock_flags = {:?}, protocol = {:?}",
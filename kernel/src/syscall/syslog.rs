@@ -0,0 +1,225 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::format;
+
+use aster_logger::{
+    console_level,
+    kmsg::{self, KMSG_BUF_LEN, KmsgCursor, KmsgError, KmsgRecord},
+};
+
+use super::SyscallReturn;
+use crate::{
+    device::register_kmsg_poller,
+    events::IoEvents,
+    prelude::*,
+    process::{
+        credentials::capabilities::CapSet,
+        signal::{PollHandle, Pollable},
+    },
+};
+
+/// The position of `SYSLOG_ACTION_READ`, which is shared by all callers.
+static SYSLOG_CURSOR: Mutex<KmsgCursor> = Mutex::new(KmsgCursor::new());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+#[repr(i32)]
+enum SyslogAction {
+    Close = 0,
+    Open = 1,
+    Read = 2,
+    ReadAll = 3,
+    ReadClear = 4,
+    Clear = 5,
+    ConsoleOff = 6,
+    ConsoleOn = 7,
+    ConsoleLevel = 8,
+    SizeUnread = 9,
+    SizeBuffer = 10,
+}
+
+pub fn sys_syslog(action: i32, buf: Vaddr, len: i32, ctx: &Context) -> Result<SyscallReturn> {
+    let action = SyslogAction::try_from(action)?;
+    debug!("action = {:?}, buf = {:#x}, len = {}", action, buf, len);
+
+    check_permission(action, ctx)?;
+
+    let res = match action {
+        SyslogAction::Close | SyslogAction::Open => 0,
+        SyslogAction::Read => {
+            let len = check_buffer(buf, len)?;
+            if len == 0 {
+                return Ok(SyscallReturn::Return(0));
+            }
+            let text = SyslogReader.wait_events(IoEvents::IN, None, || read_unread(len))?;
+            ctx.user_space().write_bytes(buf, &text)?;
+            text.len()
+        }
+        SyslogAction::ReadAll | SyslogAction::ReadClear => {
+            let len = check_buffer(buf, len)?;
+            let text = read_newest(len);
+            ctx.user_space().write_bytes(buf, text.as_bytes())?;
+            if action == SyslogAction::ReadClear {
+                kmsg::clear();
+            }
+            text.len()
+        }
+        SyslogAction::Clear => {
+            kmsg::clear();
+            0
+        }
+        SyslogAction::ConsoleOff => {
+            console_level::console_off();
+            0
+        }
+        SyslogAction::ConsoleOn => {
+            console_level::console_on();
+            0
+        }
+        SyslogAction::ConsoleLevel => {
+            if !(1..=8).contains(&len) {
+                return_errno_with_message!(Errno::EINVAL, "the console log level is invalid");
+            }
+            console_level::change_console_loglevel(len as u8);
+            0
+        }
+        SyslogAction::SizeUnread => {
+            let mut cursor = *SYSLOG_CURSOR.lock();
+            let mut size = 0;
+            while let Some(record) = read_skipping_overwritten(&mut cursor) {
+                size += format_record(&record).len();
+            }
+            size
+        }
+        SyslogAction::SizeBuffer => KMSG_BUF_LEN,
+    };
+
+    Ok(SyscallReturn::Return(res as _))
+}
+
+/// Checks whether the current thread is allowed to perform the action.
+///
+/// Like Linux with `kernel.dmesg_restrict` unset, everyone can read the whole buffer and query
+/// its size, but other actions require `CAP_SYSLOG` (or `CAP_SYS_ADMIN` for compatibility).
+fn check_permission(action: SyslogAction, ctx: &Context) -> Result<()> {
+    if matches!(action, SyslogAction::ReadAll | SyslogAction::SizeBuffer) {
+        return Ok(());
+    }
+
+    let capset = ctx.posix_thread.credentials().effective_capset();
+    if capset.contains(CapSet::SYSLOG) || capset.contains(CapSet::SYS_ADMIN) {
+        return Ok(());
+    }
+
+    return_errno_with_message!(Errno::EPERM, "the syslog action requires CAP_SYSLOG")
+}
+
+fn check_buffer(buf: Vaddr, len: i32) -> Result<usize> {
+    if buf == 0 || len < 0 {
+        return_errno_with_message!(Errno::EINVAL, "the buffer is invalid");
+    }
+
+    Ok(len as usize)
+}
+
+/// Reads the unread records that fit in `len` bytes and marks them as read.
+///
+/// If even the first record does not fit, it is truncated.
+fn read_unread(len: usize) -> Result<Vec<u8>> {
+    let mut cursor = SYSLOG_CURSOR.lock();
+    let mut text = Vec::new();
+
+    loop {
+        let mut next_cursor = *cursor;
+        let Some(record) = read_skipping_overwritten(&mut next_cursor) else {
+            break;
+        };
+
+        let line = format_record(&record);
+        if text.len() + line.len() > len {
+            if text.is_empty() {
+                text.extend_from_slice(&line.as_bytes()[..len]);
+                *cursor = next_cursor;
+            }
+            break;
+        }
+
+        text.extend_from_slice(line.as_bytes());
+        *cursor = next_cursor;
+    }
+
+    if text.is_empty() {
+        return_errno_with_message!(Errno::EAGAIN, "no unread record is available");
+    }
+
+    Ok(text)
+}
+
+/// Reads the newest records that have not been cleared and fit in `len` bytes.
+fn read_newest(len: usize) -> String {
+    let mut cursor = KmsgCursor::after_clear();
+    let mut lines = VecDeque::new();
+    let mut total_len = 0;
+
+    while let Some(record) = read_skipping_overwritten(&mut cursor) {
+        let line = format_record(&record);
+        total_len += line.len();
+        lines.push_back(line);
+
+        while total_len > len {
+            let oldest = lines.pop_front().unwrap();
+            total_len -= oldest.len();
+        }
+    }
+
+    lines.into_iter().collect()
+}
+
+fn read_skipping_overwritten(cursor: &mut KmsgCursor) -> Option<KmsgRecord> {
+    loop {
+        match cursor.read_record() {
+            Ok(record) => return Some(record),
+            Err(KmsgError::Overwritten) => continue,
+            Err(KmsgError::NoRecord) => return None,
+        }
+    }
+}
+
+/// Formats a record in the syslog format.
+///
+/// The format is `<priority>[seconds.microseconds] text\n`, where each line of a multi-line text
+/// is prefixed separately.
+fn format_record(record: &KmsgRecord) -> String {
+    let timestamp = record.timestamp();
+    let prefix = format!(
+        "<{}>[{:>5}.{:06}] ",
+        record.priority(),
+        timestamp.as_secs(),
+        timestamp.subsec_micros()
+    );
+
+    let mut text = String::new();
+    for line in String::from_utf8_lossy(record.text()).split('\n') {
+        text.push_str(&prefix);
+        text.push_str(line);
+        text.push('\n');
+    }
+
+    text
+}
+
+/// A pollable that becomes readable when there are unread records for `SYSLOG_ACTION_READ`.
+struct SyslogReader;
+
+impl Pollable for SyslogReader {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        if let Some(poller) = poller {
+            register_kmsg_poller(poller, mask);
+        }
+
+        if SYSLOG_CURSOR.lock().has_record() {
+            IoEvents::IN & mask
+        } else {
+            IoEvents::empty()
+        }
+    }
+}
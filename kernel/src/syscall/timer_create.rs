@@ -9,6 +9,7 @@ use super::{
 use crate::{
     prelude::*,
     process::{
+        credentials::capabilities::CapSet,
         posix_thread::{AsPosixThread, thread_table},
         process_table,
         signal::{
@@ -22,7 +23,7 @@ use crate::{
     thread::work_queue::{submit_work_item, work_item::WorkItem},
     time::{
         Timer, clockid_t,
-        clocks::{BootTimeClock, MonotonicClock, RealTimeClock},
+        clocks::{BootTimeClock, MonotonicClock, RealTimeClock, TaiClock},
         timer::TimerGuard,
     },
 };
@@ -141,6 +142,15 @@ where
             ClockId::CLOCK_REALTIME => RealTimeClock::timer_manager().create_timer(func),
             ClockId::CLOCK_MONOTONIC => MonotonicClock::timer_manager().create_timer(func),
             ClockId::CLOCK_BOOTTIME => BootTimeClock::timer_manager().create_timer(func),
+            ClockId::CLOCK_TAI => TaiClock::timer_manager().create_timer(func),
+            ClockId::CLOCK_REALTIME_ALARM => {
+                check_alarm_permission(ctx)?;
+                RealTimeClock::alarm_timer_manager().create_timer(func)
+            }
+            ClockId::CLOCK_BOOTTIME_ALARM => {
+                check_alarm_permission(ctx)?;
+                BootTimeClock::alarm_timer_manager().create_timer(func)
+            }
             _ => return_errno_with_message!(Errno::EINVAL, "invalid clock ID"),
         }
     } else {
@@ -172,3 +182,20 @@ where
     };
     Ok(timer)
 }
+
+/// Checks whether the current thread can use alarm timers, which may wake the system up.
+pub(super) fn check_alarm_permission(ctx: &Context) -> Result<()> {
+    if !ctx
+        .posix_thread
+        .credentials()
+        .effective_capset()
+        .contains(CapSet::WAKE_ALARM)
+    {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "alarm timers require the CAP_WAKE_ALARM capability"
+        );
+    }
+
+    Ok(())
+}
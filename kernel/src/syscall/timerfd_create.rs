@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use super::SyscallReturn;
+use super::{ClockId, SyscallReturn};
 use crate::{
     fs::file::file_table::FdFlags,
     prelude::*,
@@ -14,6 +14,17 @@ pub fn sys_timerfd_create(clockid: clockid_t, flags: i32, ctx: &Context) -> Resu
     let flags = TFDFlags::from_bits(flags as u32)
         .ok_or_else(|| Error::with_message(Errno::EINVAL, "unknown flags"))?;
 
+    if !matches!(
+        ClockId::try_from(clockid),
+        Ok(ClockId::CLOCK_REALTIME
+            | ClockId::CLOCK_MONOTONIC
+            | ClockId::CLOCK_BOOTTIME
+            | ClockId::CLOCK_REALTIME_ALARM
+            | ClockId::CLOCK_BOOTTIME_ALARM)
+    ) {
+        return_errno_with_message!(Errno::EINVAL, "the clock is not supported by timerfd");
+    }
+
     let timerfd_file = TimerfdFile::new(clockid, flags, ctx)?;
 
     let fd = {
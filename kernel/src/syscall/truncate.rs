@@ -4,7 +4,10 @@ use super::SyscallReturn;
 use crate::{
     fs,
     fs::{
-        file::file_table::{FileDesc, get_file_fast},
+        file::{
+            file_table::{FileDesc, get_file_fast},
+            lease,
+        },
         utils::PATH_MAX,
         vfs::path::{AT_FDCWD, FsPath},
     },
@@ -39,6 +42,7 @@ pub fn sys_truncate(path_ptr: Vaddr, len: isize, ctx: &Context) -> Result<Syscal
             .read()
             .lookup(&fs_path)?
     };
+    lease::break_lease(dir_path.inode().as_ref(), true, false)?;
     dir_path.resize(len as usize)?;
     fs::vfs::notify::on_change(&dir_path);
     Ok(SyscallReturn::Return(0))
@@ -19,6 +19,8 @@ pub fn handle_exception(ctx: &Context, context: &UserContext, exception: CpuExce
     debug!("[User Trap] handle exception: {:#x?}", exception);
 
     if let Ok(page_fault_info) = PageFaultInfo::try_from(&exception) {
+        crate::perf::count_page_fault(page_fault_info.address(), true);
+
         let user_space = ctx.user_space();
         let vmar = user_space.vmar();
         if handle_page_fault_from_vmar(vmar, &page_fault_info).is_ok() {
@@ -35,7 +37,7 @@ fn handle_page_fault_from_vmar(
     page_fault_info: &PageFaultInfo,
 ) -> core::result::Result<(), ()> {
     if let Err(e) = vmar.handle_page_fault(page_fault_info) {
-        warn!(
+        aster_logger::warn_ratelimited!(
             "page fault handler failed: info: {:#x?}, err: {:?}",
             page_fault_info, e
         );
@@ -60,6 +62,9 @@ pub(super) fn page_fault_handler(info: &CpuException) -> core::result::Result<()
         return Err(());
     }
 
+    let page_fault_info: PageFaultInfo = info.try_into().unwrap();
+    crate::perf::count_page_fault(page_fault_info.address(), false);
+
     let user_space = CurrentUserSpace::new(thread_local);
-    handle_page_fault_from_vmar(user_space.vmar(), &info.try_into().unwrap())
+    handle_page_fault_from_vmar(user_space.vmar(), &page_fault_info)
 }
@@ -29,6 +29,8 @@ fn pre_schedule_handler() {
     let Some(task) = Task::current() else {
         return;
     };
+    crate::perf::switch_out(&task);
+
     let Some(thread_local) = task.as_thread_local() else {
         return;
     };
@@ -44,6 +46,8 @@ fn post_schedule_handler() {
         .add_on_cpu(CpuId::current_racy(), 1);
 
     let task = Task::current().unwrap();
+    crate::perf::switch_in(&task);
+
     let Some(thread_local) = task.as_thread_local() else {
         return;
     };
@@ -10,6 +10,11 @@
 //! Though we can recover from the Rust panics. It is generally not recommended
 //! to make Rust panics as a general exception handling mechanism. Handling
 //! exceptions with [`Result`] is more idiomatic.
+//!
+//! When the kernel panics, the crash kernel is executed if it is loaded.
+//! Otherwise, the system is restarted after the timeout specified by the
+//! `panic=` kernel parameter or `/proc/sys/kernel/panic`, or it is halted if
+//! there is no timeout.
 
 use alloc::{
     boxed::Box,
@@ -19,18 +24,71 @@ use alloc::{
 };
 use core::{
     result::Result,
-    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    sync::atomic::{AtomicBool, AtomicI32, AtomicU32, AtomicUsize, Ordering},
 };
 
-use ostd::{cpu::PinCurrentCpu, panic, task::disable_preempt, util::id_set::Id};
+use ostd::{
+    cpu::PinCurrentCpu,
+    panic,
+    power::{ExitCode, restart},
+    task::disable_preempt,
+    util::id_set::Id,
+};
 
 use super::Thread;
 
-// TODO: Control the kernel commandline parsing from the kernel crate.
-// In Linux it can be dynamically changed by writing to
-// `/proc/sys/kernel/panic`.
+/// Whether the kernel panics when an oops happens.
+///
+/// It can be changed by writing to `/proc/sys/kernel/panic_on_oops`.
 static PANIC_ON_OOPS: AtomicBool = AtomicBool::new(true);
 
+/// The number of seconds to wait before restarting the system after a panic.
+///
+/// Like Linux, zero means waiting forever (i.e., the system is halted) and a
+/// negative value means restarting immediately. It can be set by the `panic=`
+/// kernel parameter or by writing to `/proc/sys/kernel/panic`.
+static PANIC_TIMEOUT: AtomicI32 = AtomicI32::new(0);
+aster_cmdline::define_kv_param!("panic", PANIC_TIMEOUT);
+
+/// Returns whether the kernel panics when an oops happens.
+pub fn panic_on_oops() -> bool {
+    PANIC_ON_OOPS.load(Ordering::Relaxed)
+}
+
+/// Sets whether the kernel panics when an oops happens.
+pub fn set_panic_on_oops(panic_on_oops: bool) {
+    PANIC_ON_OOPS.store(panic_on_oops, Ordering::Relaxed);
+}
+
+/// Returns the number of seconds to wait before restarting the system after a
+/// panic.
+pub fn panic_timeout() -> i32 {
+    PANIC_TIMEOUT.load(Ordering::Relaxed)
+}
+
+/// Sets the number of seconds to wait before restarting the system after a
+/// panic.
+pub fn set_panic_timeout(timeout: i32) {
+    PANIC_TIMEOUT.store(timeout, Ordering::Relaxed);
+}
+
+/// Returns the maximum number of oops allowed before the kernel panics.
+///
+/// Zero means that there is no limit.
+pub fn oops_limit() -> u32 {
+    OOPS_LIMIT.load(Ordering::Relaxed)
+}
+
+/// Sets the maximum number of oops allowed before the kernel panics.
+pub fn set_oops_limit(limit: u32) {
+    OOPS_LIMIT.store(limit, Ordering::Relaxed);
+}
+
+/// Returns the number of oops that have happened since boot.
+pub fn oops_count() -> usize {
+    OOPS_COUNT.load(Ordering::Relaxed)
+}
+
 /// The kernel "oops" information.
 pub struct OopsInfo {
     /// The "oops" message.
@@ -61,11 +119,12 @@ where
 
             log::error!("Oops! {}", info.message);
 
-            let count = OOPS_COUNT.fetch_add(1, Ordering::Relaxed);
-            if count >= MAX_OOPS_COUNT {
+            let count = OOPS_COUNT.fetch_add(1, Ordering::Relaxed) + 1;
+            let limit = oops_limit();
+            if limit != 0 && count >= limit as usize {
                 // Too many oops. Abort the kernel.
                 log::error!("Too many oops. The kernel panics.");
-                panic::abort();
+                halt_on_panic();
             }
 
             Err(*info)
@@ -75,8 +134,9 @@ where
 
 /// The maximum number of oops allowed before the kernel panics.
 ///
-/// It is the same as Linux's default value.
-const MAX_OOPS_COUNT: usize = 10_000;
+/// The default value is the same as Linux's. It can be changed by writing to
+/// `/proc/sys/kernel/oops_limit`.
+static OOPS_LIMIT: AtomicU32 = AtomicU32::new(10_000);
 
 static OOPS_COUNT: AtomicUsize = AtomicUsize::new(0);
 
@@ -85,8 +145,7 @@ fn panic_handler(info: &core::panic::PanicInfo) -> ! {
     let message = info.message();
 
     if let Some(thread) = Thread::current() {
-        let panic_on_oops = PANIC_ON_OOPS.load(Ordering::Relaxed);
-        if !panic_on_oops && info.can_unwind() {
+        if !panic_on_oops() && info.can_unwind() {
             // TODO: eliminate the need for heap allocation.
             let message = if let Some(location) = info.location() {
                 format!("{} at {}:{}", message, location.file(), location.line())
@@ -127,5 +186,30 @@ fn panic_handler(info: &core::panic::PanicInfo) -> ! {
         log::error!("Backtrace is disabled.");
     }
 
+    halt_on_panic();
+}
+
+/// Halts the system after the kernel panics.
+///
+/// The crash kernel is executed if it is loaded. Otherwise, the system is
+/// restarted or halted according to the panic timeout.
+fn halt_on_panic() -> ! {
+    ostd::power::crash_kexec();
+
+    let timeout = panic_timeout();
+    if timeout > 0 {
+        log::error!("Restarting the system in {} seconds...", timeout);
+
+        // Busy-wait with the TSC, since the timer interrupts may no longer work.
+        let start = ostd::arch::read_tsc();
+        let cycles = ostd::arch::tsc_freq().saturating_mul(timeout as u64);
+        while ostd::arch::read_tsc().wrapping_sub(start) < cycles {
+            core::hint::spin_loop();
+        }
+    }
+    if timeout != 0 {
+        restart(ExitCode::Failure);
+    }
+
     panic::abort();
 }
@@ -11,7 +11,7 @@ use ostd::{
 use super::{Thread, oops};
 use crate::{
     cpu::LinuxAbi,
-    current_userspace,
+    current_userspace, power,
     prelude::*,
     process::{
         posix_thread::{AsPosixThread, AsThreadLocal, ThreadLocal},
@@ -70,7 +70,11 @@ pub fn create_new_user_task(
             task: &current_task,
         };
 
-        let has_kernel_event_fn = || ctx.has_pending();
+        let has_kernel_event_fn = || {
+            ctx.has_pending()
+                || ctx.posix_thread.perf_events().has_pending_samples()
+                || power::is_freezing()
+        };
 
         if is_init_process {
             crate::init::on_first_process_startup(&ctx);
@@ -97,6 +101,9 @@ pub fn create_new_user_task(
                 ReturnReason::KernelEvent => {}
             };
 
+            // Write the samples of perf events
+            crate::perf::handle_pending_samples(&ctx, user_ctx);
+
             // Exit if the thread terminates
             if current_thread.is_exited() {
                 break;
@@ -120,6 +127,9 @@ pub fn create_new_user_task(
                 );
                 handle_pending_signal(user_ctx, &ctx, None);
             }
+
+            // Block the thread if the system is going to sleep
+            power::try_to_freeze();
         }
     };
 
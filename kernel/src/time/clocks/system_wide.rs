@@ -4,12 +4,16 @@ use alloc::sync::Arc;
 use core::time::Duration;
 
 use aster_time::read_monotonic_time;
-use ostd::{cpu::PinCurrentCpu, cpu_local, sync::SpinLock, task::disable_preempt, timer::Jiffies};
+use ostd::{cpu::CpuId, sync::SpinLock, timer::Jiffies};
 use paste::paste;
 use spin::Once;
 
-use crate::time::{
-    self, Clock, SystemTime, system_time::START_TIME_AS_DURATION, timer::TimerManager,
+use crate::{
+    cpu::hotplug::{self, CpuHotplugCallbacks},
+    time::{
+        self, Clock, SystemTime, ntp, sleep_time, system_time::START_TIME_AS_DURATION,
+        timer::TimerManager,
+    },
 };
 
 /// The Clock that reads the jiffies, and turn the counter into `Duration`.
@@ -28,13 +32,18 @@ impl RealTimeClock {
         CLOCK_REALTIME_INSTANCE.get().unwrap()
     }
 
-    /// Get the cpu-local system-wide `TimerManager` singleton of this clock.
+    /// Get the system-wide `TimerManager` singleton of this clock.
+    ///
+    /// The `TimerManager` is per-CPU, so its timers expire with a high resolution.
     pub fn timer_manager() -> &'static Arc<TimerManager> {
-        let preempt_guard = disable_preempt();
-        CLOCK_REALTIME_MANAGER
-            .get_on_cpu(preempt_guard.current_cpu())
-            .get()
-            .unwrap()
+        CLOCK_REALTIME_MANAGER.get().unwrap()
+    }
+
+    /// Get the system-wide `TimerManager` singleton of the alarm timers of this clock.
+    ///
+    /// Unlike other timers, alarm timers should wake the system up from suspend.
+    pub fn alarm_timer_manager() -> &'static Arc<TimerManager> {
+        CLOCK_REALTIME_ALARM_MANAGER.get().unwrap()
     }
 }
 
@@ -50,13 +59,11 @@ impl MonotonicClock {
         CLOCK_MONOTONIC_INSTANCE.get().unwrap()
     }
 
-    /// Get the cpu-local system-wide `TimerManager` singleton of this clock.
+    /// Get the system-wide `TimerManager` singleton of this clock.
+    ///
+    /// The `TimerManager` is per-CPU, so its timers expire with a high resolution.
     pub fn timer_manager() -> &'static Arc<TimerManager> {
-        let preempt_guard = disable_preempt();
-        CLOCK_MONOTONIC_MANAGER
-            .get_on_cpu(preempt_guard.current_cpu())
-            .get()
-            .unwrap()
+        CLOCK_MONOTONIC_MANAGER.get().unwrap()
     }
 }
 
@@ -120,9 +127,6 @@ impl MonotonicRawClock {
 
 /// `BootTimeClock` measures the time elapsed since the system was booted,
 /// including time when the system was suspended.
-///
-/// Note: currently the system will not be suspended so we treat this clock
-/// as the [`MonotonicClock`].
 pub struct BootTimeClock {
     _private: (),
 }
@@ -133,13 +137,40 @@ impl BootTimeClock {
         CLOCK_BOOTTIME_INSTANCE.get().unwrap()
     }
 
-    /// Get the cpu-local system-wide `TimerManager` singleton of this clock.
+    /// Get the system-wide `TimerManager` singleton of this clock.
+    ///
+    /// The `TimerManager` is per-CPU, so its timers expire with a high resolution.
     pub fn timer_manager() -> &'static Arc<TimerManager> {
-        let preempt_guard = disable_preempt();
-        CLOCK_BOOTTIME_MANAGER
-            .get_on_cpu(preempt_guard.current_cpu())
-            .get()
-            .unwrap()
+        CLOCK_BOOTTIME_MANAGER.get().unwrap()
+    }
+
+    /// Get the system-wide `TimerManager` singleton of the alarm timers of this clock.
+    ///
+    /// Unlike other timers, alarm timers should wake the system up from suspend.
+    pub fn alarm_timer_manager() -> &'static Arc<TimerManager> {
+        CLOCK_BOOTTIME_ALARM_MANAGER.get().unwrap()
+    }
+}
+
+/// `TaiClock` provides the International Atomic Time (TAI).
+///
+/// Unlike [`RealTimeClock`], this clock does not count leap seconds. It is ahead of
+/// [`RealTimeClock`] by the TAI offset, which is set by the user space with `adjtimex`.
+pub struct TaiClock {
+    _private: (),
+}
+
+impl TaiClock {
+    /// Get the singleton of this clock.
+    pub fn get() -> &'static Arc<TaiClock> {
+        CLOCK_TAI_INSTANCE.get().unwrap()
+    }
+
+    /// Get the system-wide `TimerManager` singleton of this clock.
+    ///
+    /// The `TimerManager` is per-CPU, so its timers expire with a high resolution.
+    pub fn timer_manager() -> &'static Arc<TimerManager> {
+        CLOCK_TAI_MANAGER.get().unwrap()
     }
 }
 
@@ -171,7 +202,9 @@ impl Clock for RealTimeCoarseClock {
 
 impl Clock for MonotonicCoarseClock {
     fn read_time(&self) -> Duration {
-        RealTimeCoarseClock::get().read_time() - *START_TIME_AS_DURATION.get().unwrap()
+        // The record may be stale when the system has just resumed from suspend.
+        (RealTimeCoarseClock::get().read_time() - *START_TIME_AS_DURATION.get().unwrap())
+            .saturating_sub(sleep_time())
     }
 }
 
@@ -183,7 +216,13 @@ impl Clock for MonotonicRawClock {
 
 impl Clock for BootTimeClock {
     fn read_time(&self) -> Duration {
-        read_monotonic_time()
+        read_monotonic_time() + sleep_time()
+    }
+}
+
+impl Clock for TaiClock {
+    fn read_time(&self) -> Duration {
+        RealTimeClock::get().read_time() + ntp::tai_offset()
     }
 }
 
@@ -216,26 +255,20 @@ macro_rules! define_timer_managers {
     ($($clock_id:ident,)*) => {
         $(
             paste! {
-                cpu_local! {
-                    pub static [<$clock_id _MANAGER>]: Once<Arc<TimerManager>> = Once::new();
-                }
+                pub static [<$clock_id _MANAGER>]: Once<Arc<TimerManager>> = Once::new();
             }
         )*
 
         fn _init_system_wide_timer_managers() {
             $(
                 let clock = paste! {[<$clock_id _INSTANCE>].get().unwrap().clone()};
-                let timer_manager = TimerManager::new(clock);
-                for cpu in ostd::cpu::all_cpus() {
-                    paste! {
-                        [<$clock_id _MANAGER>].get_on_cpu(cpu).call_once(|| timer_manager.clone());
-                    }
+                let timer_manager = TimerManager::new_per_cpu(clock);
+                paste! {
+                    [<$clock_id _MANAGER>].call_once(|| timer_manager);
                 }
                 let callback = || {
-                    let preempt_guard = ostd::task::disable_preempt();
-                    let cpu = preempt_guard.current_cpu();
                     paste! {
-                        [<$clock_id _MANAGER>].get_on_cpu(cpu).get().unwrap().process_expired_timers();
+                        [<$clock_id _MANAGER>].get().unwrap().process_expired_timers();
                     }
                 };
                 time::softirq::register_callback(callback);
@@ -251,16 +284,17 @@ define_system_clocks! {
     CLOCK_MONOTONIC_COARSE  => MonotonicCoarseClock,
     CLOCK_MONOTONIC_RAW     => MonotonicRawClock,
     CLOCK_BOOTTIME          => BootTimeClock,
+    CLOCK_TAI               => TaiClock,
 }
 
-define_timer_managers![CLOCK_REALTIME, CLOCK_MONOTONIC, CLOCK_BOOTTIME,];
+define_timer_managers![CLOCK_REALTIME, CLOCK_MONOTONIC, CLOCK_BOOTTIME, CLOCK_TAI,];
 
 /// Init the system-wide clocks.
 fn init_system_wide_clocks() {
     _init_system_wide_clocks();
 }
 
-/// Init the system-wide cpu-local [`TimerManager`]s.
+/// Init the system-wide per-CPU [`TimerManager`]s.
 fn init_system_wide_timer_managers() {
     _init_system_wide_timer_managers();
 }
@@ -270,7 +304,8 @@ pub static JIFFIES_TIMER_MANAGER: Once<Arc<TimerManager>> = Once::new();
 
 fn init_jiffies_clock_manager() {
     let jiffies_clock = JiffiesClock { _private: () };
-    let jiffies_timer_manager = TimerManager::new(Arc::new(jiffies_clock));
+    // The jiffies are calculated from the clock source, so the timers can be per-CPU.
+    let jiffies_timer_manager = TimerManager::new_per_cpu(Arc::new(jiffies_clock));
     JIFFIES_TIMER_MANAGER.call_once(|| jiffies_timer_manager);
 
     let callback = || {
@@ -282,6 +317,42 @@ fn init_jiffies_clock_manager() {
     time::softirq::register_callback(callback);
 }
 
+/// The system-wide [`TimerManager`] for the alarm timers of the [`RealTimeClock`].
+static CLOCK_REALTIME_ALARM_MANAGER: Once<Arc<TimerManager>> = Once::new();
+/// The system-wide [`TimerManager`] for the alarm timers of the [`BootTimeClock`].
+static CLOCK_BOOTTIME_ALARM_MANAGER: Once<Arc<TimerManager>> = Once::new();
+
+fn init_alarm_timer_managers() {
+    let realtime_clock = CLOCK_REALTIME_INSTANCE.get().unwrap().clone();
+    CLOCK_REALTIME_ALARM_MANAGER.call_once(|| TimerManager::new_per_cpu(realtime_clock));
+    let boottime_clock = CLOCK_BOOTTIME_INSTANCE.get().unwrap().clone();
+    CLOCK_BOOTTIME_ALARM_MANAGER.call_once(|| TimerManager::new_per_cpu(boottime_clock));
+
+    let callback = || {
+        RealTimeClock::alarm_timer_manager().process_expired_timers();
+        BootTimeClock::alarm_timer_manager().process_expired_timers();
+    };
+    time::softirq::register_callback(callback);
+}
+
+/// Returns the time until the earliest alarm timer expires, if any.
+///
+/// Before the system is suspended, a wakeup source should be programmed with this time, so that
+/// the alarm timers can wake the system up.
+#[expect(dead_code)]
+pub fn next_alarm_timeout() -> Option<Duration> {
+    [
+        RealTimeClock::alarm_timer_manager(),
+        BootTimeClock::alarm_timer_manager(),
+    ]
+    .into_iter()
+    .filter_map(|timer_manager| {
+        let expired_time = timer_manager.next_expired_time()?;
+        Some(expired_time.saturating_sub(timer_manager.clock().read_time()))
+    })
+    .min()
+}
+
 fn update_coarse_clock() {
     let real_time = RealTimeClock::get().read_time();
     let current = RealTimeCoarseClock::current_ref().get().unwrap();
@@ -294,26 +365,51 @@ fn init_coarse_clock() {
     time::softirq::register_callback(update_coarse_clock);
 }
 
+/// Moves the timers of the per-CPU [`TimerManager`]s away from an offline CPU.
+fn migrate_timers_from(cpu: CpuId) {
+    let timer_managers = [
+        CLOCK_REALTIME_MANAGER.get(),
+        CLOCK_MONOTONIC_MANAGER.get(),
+        CLOCK_BOOTTIME_MANAGER.get(),
+        CLOCK_TAI_MANAGER.get(),
+        CLOCK_REALTIME_ALARM_MANAGER.get(),
+        CLOCK_BOOTTIME_ALARM_MANAGER.get(),
+        JIFFIES_TIMER_MANAGER.get(),
+    ];
+    for timer_manager in timer_managers.into_iter().flatten() {
+        timer_manager.migrate_timers_from(cpu);
+    }
+}
+
 pub(super) fn init() {
     init_system_wide_clocks();
     init_system_wide_timer_managers();
+    init_alarm_timer_managers();
     init_jiffies_clock_manager();
     init_coarse_clock();
+
+    hotplug::register_callbacks(CpuHotplugCallbacks {
+        name: "timers",
+        online: |_| {},
+        offline: migrate_timers_from,
+    });
 }
 
 #[cfg(ktest)]
-/// Init `CLOCK_REALTIME_MANAGER` for process-related ktests.
+/// Init `CLOCK_REALTIME_MANAGER` and `CLOCK_MONOTONIC_MANAGER` for process-related ktests.
 ///
 /// TODO: `ktest` may require a feature that allows the registration of initialization functions
 /// to avoid functions like this one.
 pub fn init_for_ktest() {
     // If `spin::Once` has initialized, this closure will not be executed.
-    for cpu in ostd::cpu::all_cpus() {
-        CLOCK_REALTIME_MANAGER.get_on_cpu(cpu).call_once(|| {
-            let clock = RealTimeClock { _private: () };
-            TimerManager::new(Arc::new(clock))
-        });
-    }
+    CLOCK_REALTIME_MANAGER.call_once(|| {
+        let clock = RealTimeClock { _private: () };
+        TimerManager::new_per_cpu(Arc::new(clock))
+    });
+    CLOCK_MONOTONIC_MANAGER.call_once(|| {
+        let clock = MonotonicClock { _private: () };
+        TimerManager::new_per_cpu(Arc::new(clock))
+    });
     CLOCK_REALTIME_COARSE_INSTANCE.call_once(|| Arc::new(RealTimeCoarseClock { _private: () }));
     RealTimeCoarseClock::current_ref().call_once(|| SpinLock::new(Duration::from_secs(0)));
     JIFFIES_TIMER_MANAGER.call_once(|| {
@@ -11,7 +11,11 @@ use core::{
     time::Duration,
 };
 
-use ostd::sync::{LocalIrqDisabled, SpinLock, SpinLockGuard};
+use ostd::{
+    cpu::{CpuId, PinCurrentCpu},
+    sync::{LocalIrqDisabled, SpinLock, SpinLockGuard},
+    task::disable_preempt,
+};
 
 use super::Clock;
 
@@ -155,17 +159,44 @@ impl Timer {
 ///
 /// These created `Timer`s will hold an `Arc` pointer to this manager, hence this manager
 /// will be actually dropped after all the created timers have been dropped.
+///
+/// A per-CPU `TimerManager` (created with [`TimerManager::new_per_cpu`]) keeps a queue of timers
+/// for each CPU. A timer is queued on the CPU where it is set, and the expiry of the earliest
+/// timer on each CPU is programmed as a one-shot timer event with [`ostd::timer::set_next_event`].
+/// Therefore, the timers expire with a high resolution instead of at timer ticks, as long as
+/// [`TimerManager::process_expired_timers`] is called on each CPU when the event expires.
 pub struct TimerManager {
     clock: Arc<dyn Clock>,
-    timer_callbacks: SpinLock<BinaryHeap<Arc<TimerCallback>>>,
+    /// The queues of the timers, one for each CPU if the manager is per-CPU.
+    timer_callbacks: Box<[SpinLock<BinaryHeap<Arc<TimerCallback>>>]>,
+    is_per_cpu: bool,
 }
 
 impl TimerManager {
     /// Creates a `TimerManager` instance from a clock.
+    ///
+    /// The timers are checked at timer ticks, so the clock may be a coarse clock.
     pub fn new(clock: Arc<dyn Clock>) -> Arc<Self> {
         Arc::new(Self {
             clock,
-            timer_callbacks: SpinLock::new(BinaryHeap::new()),
+            timer_callbacks: Box::new([SpinLock::new(BinaryHeap::new())]),
+            is_per_cpu: false,
+        })
+    }
+
+    /// Creates a per-CPU `TimerManager` instance from a clock.
+    ///
+    /// The clock must be read from the clock source, instead of being updated at timer ticks.
+    /// Otherwise, the timers may not expire in time when the one-shot timer events expire.
+    pub fn new_per_cpu(clock: Arc<dyn Clock>) -> Arc<Self> {
+        let timer_callbacks = ostd::cpu::all_cpus()
+            .map(|_| SpinLock::new(BinaryHeap::new()))
+            .collect();
+
+        Arc::new(Self {
+            clock,
+            timer_callbacks,
+            is_per_cpu: true,
         })
     }
 
@@ -185,19 +216,50 @@ impl TimerManager {
         }
     }
 
+    /// Returns the queue of the timers on the given CPU.
+    fn timer_queue(&self, cpu: CpuId) -> &SpinLock<BinaryHeap<Arc<TimerCallback>>> {
+        if self.is_per_cpu {
+            &self.timer_callbacks[cpu.as_usize()]
+        } else {
+            &self.timer_callbacks[0]
+        }
+    }
+
     fn insert(&self, timer_callback: Arc<TimerCallback>) {
-        self.timer_callbacks
-            .disable_irq()
-            .lock()
-            .push(timer_callback);
+        let preempt_guard = disable_preempt();
+        let timer_queue = self.timer_queue(preempt_guard.current_cpu());
+        let mut timeout_list = timer_queue.disable_irq().lock();
+
+        let expired_time = timer_callback.expired_time;
+        let is_earliest = timeout_list
+            .peek()
+            .is_none_or(|t| expired_time < t.expired_time);
+        timeout_list.push(timer_callback);
+
+        if self.is_per_cpu && is_earliest {
+            self.set_next_event(expired_time);
+        }
+    }
+
+    /// Sets the one-shot timer event on the current CPU for a timer that expires at
+    /// `expired_time`.
+    fn set_next_event(&self, expired_time: Duration) {
+        let delay = expired_time.saturating_sub(self.clock.read_time());
+        ostd::timer::set_next_event(delay);
     }
 
     /// Checks and processes the managed timers.
     ///
     /// If any of the timers have timed out, call the corresponding callback functions.
+    ///
+    /// For a per-CPU `TimerManager`, only the timers on the current CPU are processed, and the
+    /// one-shot timer event is set for the earliest remaining timer on the current CPU.
     pub fn process_expired_timers(&self) {
+        let preempt_guard = disable_preempt();
+        let timer_queue = self.timer_queue(preempt_guard.current_cpu());
+
         let callbacks = {
-            let mut timeout_list = self.timer_callbacks.disable_irq().lock();
+            let mut timeout_list = timer_queue.disable_irq().lock();
             if timeout_list.is_empty() {
                 return;
             }
@@ -220,6 +282,73 @@ impl TimerManager {
         for callback in callbacks {
             callback.call();
         }
+
+        if !self.is_per_cpu {
+            return;
+        }
+        // The callbacks may set new timers, which have set the one-shot timer events. But the
+        // earliest remaining timer may still need an event, since the event that has expired is
+        // consumed.
+        let next_expired_time = Self::first_expired_time(&mut timer_queue.disable_irq().lock());
+        if let Some(expired_time) = next_expired_time {
+            self.set_next_event(expired_time);
+        }
+    }
+
+    /// Moves the timers on the given CPU to the current CPU.
+    ///
+    /// This method should be called after the given CPU is taken offline, since the timers on an
+    /// offline CPU never expire. It does nothing if the manager is not per-CPU.
+    pub fn migrate_timers_from(&self, cpu: CpuId) {
+        if !self.is_per_cpu {
+            return;
+        }
+
+        let preempt_guard = disable_preempt();
+        let current_cpu = preempt_guard.current_cpu();
+        if current_cpu == cpu {
+            return;
+        }
+
+        let timers = core::mem::take(&mut *self.timer_queue(cpu).disable_irq().lock());
+        if timers.is_empty() {
+            return;
+        }
+
+        let next_expired_time = {
+            let mut timeout_list = self.timer_queue(current_cpu).disable_irq().lock();
+            timeout_list.extend(timers);
+            Self::first_expired_time(&mut timeout_list)
+        };
+        if let Some(expired_time) = next_expired_time {
+            self.set_next_event(expired_time);
+        }
+    }
+
+    /// Returns the earliest time at which one of the managed timers expires.
+    ///
+    /// The time is measured by the clock of this `TimerManager`. If there are no active timers,
+    /// this method returns `None`.
+    pub fn next_expired_time(&self) -> Option<Duration> {
+        self.timer_callbacks
+            .iter()
+            .filter_map(|timer_queue| {
+                Self::first_expired_time(&mut timer_queue.disable_irq().lock())
+            })
+            .min()
+    }
+
+    /// Returns the expired time of the first active timer in a queue.
+    ///
+    /// The cancelled timers at the front of the queue are removed.
+    fn first_expired_time(timeout_list: &mut BinaryHeap<Arc<TimerCallback>>) -> Option<Duration> {
+        while let Some(t) = timeout_list.peek() {
+            if !t.is_cancelled() {
+                return Some(t.expired_time);
+            }
+            timeout_list.pop();
+        }
+        None
     }
 
     /// Creates an [`Timer`], which will be managed by this `TimerManager`.
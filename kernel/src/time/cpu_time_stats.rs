@@ -67,11 +67,15 @@ impl CpuTimeStatsManager {
 
     /// Collects the time statistics on the specific CPU.  
     pub fn collect_stats_on_cpu(&self, cpu: CpuId) -> CpuTimeStats {
+        let mut idle = Jiffies::new(self.idle.get_on_cpu(cpu) as u64);
+        // The ticks are skipped when the CPU is idle.
+        idle.add(ostd::timer::skipped_ticks_on_cpu(cpu).as_u64());
+
         CpuTimeStats {
             user: Jiffies::new(self.user.get_on_cpu(cpu) as u64),
             nice: Jiffies::new(self.nice.get_on_cpu(cpu) as u64),
             system: Jiffies::new(self.system.get_on_cpu(cpu) as u64),
-            idle: Jiffies::new(self.idle.get_on_cpu(cpu) as u64),
+            idle,
             iowait: Jiffies::new(self.iowait.get_on_cpu(cpu) as u64),
             irq: Jiffies::new(self.irq.get_on_cpu(cpu) as u64),
             softirq: Jiffies::new(self.softirq.get_on_cpu(cpu) as u64),
@@ -83,11 +87,17 @@ impl CpuTimeStatsManager {
 
     /// Collects the time statistics across all CPUs.
     pub fn collect_stats_on_all_cpus(&self) -> CpuTimeStats {
+        let mut idle = Jiffies::new(self.idle.sum_all_cpus() as u64);
+        // The ticks are skipped when the CPUs are idle.
+        for cpu in ostd::cpu::all_cpus() {
+            idle.add(ostd::timer::skipped_ticks_on_cpu(cpu).as_u64());
+        }
+
         CpuTimeStats {
             user: Jiffies::new(self.user.sum_all_cpus() as u64),
             nice: Jiffies::new(self.nice.sum_all_cpus() as u64),
             system: Jiffies::new(self.system.sum_all_cpus() as u64),
-            idle: Jiffies::new(self.idle.sum_all_cpus() as u64),
+            idle,
             iowait: Jiffies::new(self.iowait.sum_all_cpus() as u64),
             irq: Jiffies::new(self.irq.sum_all_cpus() as u64),
             softirq: Jiffies::new(self.softirq.sum_all_cpus() as u64),
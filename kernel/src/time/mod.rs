@@ -5,7 +5,7 @@
 pub use core::{Clock, timer};
 
 use ::core::time::Duration;
-pub use system_time::{START_TIME, SystemTime};
+pub use system_time::{START_TIME, SystemTime, sleep_time};
 pub use timer::{Timer, TimerManager};
 
 use crate::prelude::*;
@@ -13,6 +13,7 @@ use crate::prelude::*;
 pub mod clocks;
 mod core;
 pub mod cpu_time_stats;
+pub mod ntp;
 mod softirq;
 mod system_time;
 pub mod timerfd;
@@ -0,0 +1,307 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The NTP state of the kernel.
+//!
+//! User space (e.g., an NTP daemon) reads and updates the state with `adjtimex`. Currently, the
+//! kernel does not discipline its clocks, so the adjustments that require slewing or stepping the
+//! clocks are not supported. The other parts of the state are maintained as in Linux, including
+//! the TAI offset that [`TaiClock`] depends on.
+//!
+//! Reference: <https://elixir.bootlin.com/linux/v6.18/source/kernel/time/ntp.c>.
+//!
+//! [`TaiClock`]: super::clocks::TaiClock
+
+use core::time::Duration;
+
+use aster_time::read_monotonic_time;
+use ostd::sync::SpinLock;
+
+use super::{Clock, NSEC_PER_USEC, USEC_PER_SEC, clocks::RealTimeClock, timeval_t};
+use crate::prelude::*;
+
+/// The `timex` structure used by `adjtimex` and `clock_adjtime`.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/timex.h>.
+#[repr(C)]
+#[padding_struct]
+#[derive(Debug, Default, Copy, Clone, Pod)]
+pub struct timex_t {
+    pub modes: u32,
+    pub offset: i64,
+    pub freq: i64,
+    pub maxerror: i64,
+    pub esterror: i64,
+    pub status: i32,
+    pub constant: i64,
+    pub precision: i64,
+    pub tolerance: i64,
+    pub time: timeval_t,
+    pub tick: i64,
+    pub ppsfreq: i64,
+    pub jitter: i64,
+    pub shift: i32,
+    pub stabil: i64,
+    pub jitcnt: i64,
+    pub calcnt: i64,
+    pub errcnt: i64,
+    pub stbcnt: i64,
+    pub tai: i32,
+    _reserved: [i32; 11],
+}
+
+bitflags! {
+    /// The modes of `adjtimex`, which select the fields to update.
+    pub struct AdjModes: u32 {
+        const ADJ_OFFSET = 0x0001;
+        const ADJ_FREQUENCY = 0x0002;
+        const ADJ_MAXERROR = 0x0004;
+        const ADJ_ESTERROR = 0x0008;
+        const ADJ_STATUS = 0x0010;
+        const ADJ_TIMECONST = 0x0020;
+        const ADJ_TAI = 0x0080;
+        const ADJ_SETOFFSET = 0x0100;
+        const ADJ_MICRO = 0x1000;
+        const ADJ_NANO = 0x2000;
+        const ADJ_TICK = 0x4000;
+        /// The mode used by `adjtime`, which must be combined with `ADJ_OFFSET`.
+        const ADJ_ADJTIME = 0x8000;
+        /// The flag that makes `ADJ_ADJTIME` read-only.
+        const ADJ_OFFSET_READONLY = 0x2000;
+    }
+}
+
+impl AdjModes {
+    /// Returns whether the modes only read the NTP state.
+    ///
+    /// Updating the NTP state requires the `CAP_SYS_TIME` capability.
+    pub fn is_read_only(&self) -> bool {
+        if self.contains(Self::ADJ_ADJTIME) {
+            self.contains(Self::ADJ_OFFSET_READONLY)
+        } else {
+            self.is_empty()
+        }
+    }
+}
+
+bitflags! {
+    /// The NTP status flags.
+    pub struct NtpStatus: i32 {
+        const STA_PLL = 0x0001;
+        const STA_PPSFREQ = 0x0002;
+        const STA_PPSTIME = 0x0004;
+        const STA_FLL = 0x0008;
+        const STA_INS = 0x0010;
+        const STA_DEL = 0x0020;
+        const STA_UNSYNC = 0x0040;
+        const STA_FREQHOLD = 0x0080;
+        const STA_PPSSIGNAL = 0x0100;
+        const STA_PPSJITTER = 0x0200;
+        const STA_PPSWANDER = 0x0400;
+        const STA_PPSERROR = 0x0800;
+        const STA_CLOCKERR = 0x1000;
+        const STA_NANO = 0x2000;
+        const STA_MODE = 0x4000;
+        const STA_CLK = 0x8000;
+        /// The read-only flags, which cannot be set with `ADJ_STATUS`.
+        const STA_RONLY = Self::STA_PPSSIGNAL.bits()
+            | Self::STA_PPSJITTER.bits()
+            | Self::STA_PPSWANDER.bits()
+            | Self::STA_PPSERROR.bits()
+            | Self::STA_CLOCKERR.bits()
+            | Self::STA_NANO.bits()
+            | Self::STA_MODE.bits()
+            | Self::STA_CLK.bits();
+    }
+}
+
+/// The clock state returned by `adjtimex` if the clock is synchronized.
+const TIME_OK: i32 = 0;
+/// The clock state returned by `adjtimex` if the clock is not synchronized.
+const TIME_ERROR: i32 = 5;
+
+/// The maximum time constant of the PLL.
+const MAXTC: i64 = 10;
+/// The maximum TAI offset, in seconds.
+const MAX_TAI_OFFSET: i64 = 100_000;
+/// The maximum error, in microseconds, beyond which the clock is considered unsynchronized.
+const NTP_PHASE_LIMIT: i64 = 16_000_000;
+/// The growth of the maximum error per second, in microseconds.
+const MAXERROR_PER_SEC: i64 = 500;
+/// The frequency tolerance, in scaled parts per million.
+const TOLERANCE: i64 = 500 << 16;
+/// The number of user ticks per second.
+const USER_HZ: i64 = 100;
+
+static NTP_STATE: SpinLock<NtpState> = SpinLock::new(NtpState::new());
+
+struct NtpState {
+    status: NtpStatus,
+    maxerror: i64,
+    esterror: i64,
+    constant: i64,
+    tai_offset: u32,
+    /// The monotonic time in seconds when `maxerror` was last updated.
+    last_update_secs: u64,
+}
+
+impl NtpState {
+    const fn new() -> Self {
+        Self {
+            status: NtpStatus::STA_UNSYNC,
+            maxerror: NTP_PHASE_LIMIT,
+            esterror: NTP_PHASE_LIMIT,
+            constant: 2,
+            tai_offset: 0,
+            last_update_secs: 0,
+        }
+    }
+
+    /// Grows the maximum error as time elapses, like Linux does once per second.
+    fn update(&mut self) {
+        let now_secs = read_monotonic_time().as_secs();
+        let elapsed_secs = now_secs.saturating_sub(self.last_update_secs);
+        self.last_update_secs = now_secs;
+
+        let growth = MAXERROR_PER_SEC.saturating_mul(elapsed_secs.try_into().unwrap_or(i64::MAX));
+        self.maxerror = self.maxerror.saturating_add(growth);
+        if self.maxerror > NTP_PHASE_LIMIT {
+            self.maxerror = NTP_PHASE_LIMIT;
+            self.status |= NtpStatus::STA_UNSYNC;
+        }
+    }
+
+    fn is_synced(&self) -> bool {
+        !self.status.contains(NtpStatus::STA_UNSYNC)
+    }
+
+    fn is_error_status(&self) -> bool {
+        let status = self.status;
+
+        status.intersects(NtpStatus::STA_UNSYNC | NtpStatus::STA_CLOCKERR)
+            || (status.intersects(NtpStatus::STA_PPSFREQ | NtpStatus::STA_PPSTIME)
+                && !status.contains(NtpStatus::STA_PPSSIGNAL))
+            || status.contains(NtpStatus::STA_PPSTIME | NtpStatus::STA_PPSJITTER)
+            || (status.contains(NtpStatus::STA_PPSFREQ)
+                && status.intersects(NtpStatus::STA_PPSWANDER | NtpStatus::STA_PPSERROR))
+    }
+}
+
+/// Returns the offset of TAI from UTC.
+pub fn tai_offset() -> Duration {
+    Duration::from_secs(NTP_STATE.disable_irq().lock().tai_offset as u64)
+}
+
+/// Returns whether the clock is synchronized by NTP.
+pub fn is_synced() -> bool {
+    let mut state = NTP_STATE.disable_irq().lock();
+    state.update();
+    state.is_synced()
+}
+
+/// Reads and updates the NTP state as specified by the `timex` structure.
+///
+/// The caller should check whether the current thread has the permission to update the state
+/// (see [`AdjModes::is_read_only`]).
+/// On success, the `timex` structure is filled with the current state and the clock state is
+/// returned.
+pub fn do_adjtimex(timex: &mut timex_t) -> Result<i32> {
+    let modes = AdjModes::from_bits_truncate(timex.modes);
+
+    if modes.contains(AdjModes::ADJ_ADJTIME) {
+        if !modes.contains(AdjModes::ADJ_OFFSET) {
+            return_errno_with_message!(Errno::EINVAL, "`ADJ_ADJTIME` requires `ADJ_OFFSET`");
+        }
+        if !modes.contains(AdjModes::ADJ_OFFSET_READONLY) {
+            return_errno_with_message!(Errno::EOPNOTSUPP, "slewing the clock is not supported");
+        }
+    } else if modes.intersects(
+        AdjModes::ADJ_OFFSET
+            | AdjModes::ADJ_FREQUENCY
+            | AdjModes::ADJ_SETOFFSET
+            | AdjModes::ADJ_TICK,
+    ) {
+        return_errno_with_message!(Errno::EOPNOTSUPP, "adjusting the clock is not supported");
+    }
+
+    let mut state = NTP_STATE.disable_irq().lock();
+    state.update();
+    let was_synced = state.is_synced();
+
+    if !modes.contains(AdjModes::ADJ_ADJTIME) {
+        if modes.contains(AdjModes::ADJ_STATUS) {
+            let status = NtpStatus::from_bits_truncate(timex.status);
+            if state.status.contains(NtpStatus::STA_PLL) && !status.contains(NtpStatus::STA_PLL) {
+                state.status = NtpStatus::STA_UNSYNC;
+            }
+            state.status &= NtpStatus::STA_RONLY;
+            state.status |= status - NtpStatus::STA_RONLY;
+        }
+
+        if modes.contains(AdjModes::ADJ_NANO) {
+            state.status |= NtpStatus::STA_NANO;
+        }
+        if modes.contains(AdjModes::ADJ_MICRO) {
+            state.status -= NtpStatus::STA_NANO;
+        }
+
+        if modes.contains(AdjModes::ADJ_MAXERROR) {
+            state.maxerror = timex.maxerror.clamp(0, NTP_PHASE_LIMIT);
+        }
+        if modes.contains(AdjModes::ADJ_ESTERROR) {
+            state.esterror = timex.esterror.clamp(0, NTP_PHASE_LIMIT);
+        }
+
+        if modes.contains(AdjModes::ADJ_TIMECONST) {
+            let mut constant = timex.constant;
+            if !state.status.contains(NtpStatus::STA_NANO) {
+                constant = constant.saturating_add(4);
+            }
+            state.constant = constant.clamp(0, MAXTC);
+        }
+
+        // Like Linux, an invalid TAI offset is silently ignored.
+        if modes.contains(AdjModes::ADJ_TAI) && (0..=MAX_TAI_OFFSET).contains(&timex.constant) {
+            state.tai_offset = timex.constant as u32;
+        }
+    }
+
+    let now = RealTimeClock::get().read_time();
+    let subsec = if state.status.contains(NtpStatus::STA_NANO) {
+        now.subsec_nanos() as i64
+    } else {
+        now.subsec_nanos() as i64 / NSEC_PER_USEC
+    };
+
+    *timex = timex_t {
+        modes: timex.modes,
+        maxerror: state.maxerror,
+        esterror: state.esterror,
+        status: state.status.bits(),
+        constant: state.constant,
+        precision: 1,
+        tolerance: TOLERANCE,
+        time: timeval_t {
+            sec: now.as_secs() as i64,
+            usec: subsec,
+        },
+        tick: USEC_PER_SEC / USER_HZ,
+        tai: state.tai_offset as i32,
+        ..Default::default()
+    };
+
+    let clock_state = if state.is_error_status() {
+        TIME_ERROR
+    } else {
+        TIME_OK
+    };
+
+    let is_synced = state.is_synced();
+    drop(state);
+
+    // The system time will be written back to the RTC once the clock is synchronized.
+    if is_synced && !was_synced {
+        crate::device::notify_ntp_synced();
+    }
+
+    Ok(clock_state)
+}
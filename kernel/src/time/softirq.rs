@@ -14,6 +14,10 @@ pub(super) fn init() {
     timer::register_callback_on_cpu(|| {
         SoftIrqLine::get(TIMER_SOFTIRQ_ID).raise();
     });
+    // The per-CPU timer managers set one-shot timer events for their earliest timers.
+    timer::register_event_handler(|| {
+        SoftIrqLine::get(TIMER_SOFTIRQ_ID).raise();
+    });
 }
 
 /// Registers a function that will be executed during timer softirq.
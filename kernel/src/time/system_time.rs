@@ -1,6 +1,9 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use core::time::Duration;
+use core::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
 
 use aster_time::{read_monotonic_time, read_start_time};
 use spin::Once;
@@ -15,6 +18,9 @@ pub struct SystemTime(PrimitiveDateTime);
 pub static START_TIME: Once<SystemTime> = Once::new();
 pub(super) static START_TIME_AS_DURATION: Once<Duration> = Once::new();
 
+/// The total time, in nanoseconds, that the system has spent in suspend.
+static SLEEP_TIME_NANOS: AtomicU64 = AtomicU64::new(0);
+
 pub(super) fn init() {
     let start_time = convert_system_time(read_start_time()).unwrap();
     START_TIME_AS_DURATION
@@ -22,6 +28,20 @@ pub(super) fn init() {
     START_TIME.call_once(|| start_time);
 }
 
+/// Returns the total time that the system has spent in suspend.
+pub fn sleep_time() -> Duration {
+    Duration::from_nanos(SLEEP_TIME_NANOS.load(Ordering::Relaxed))
+}
+
+/// Accounts for the time that the system has spent in suspend.
+///
+/// The monotonic time stops while the system is suspended. This should be called when the system
+/// resumes, so that the real time and the boot time keep counting across suspend.
+#[expect(dead_code)]
+pub fn inject_sleep_time(duration: Duration) {
+    SLEEP_TIME_NANOS.fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+}
+
 impl SystemTime {
     /// The unix epoch, which represents 1970-01-01 00:00:00
     pub const UNIX_EPOCH: SystemTime = SystemTime::unix_epoch();
@@ -44,7 +64,7 @@ impl SystemTime {
         START_TIME
             .get()
             .unwrap()
-            .checked_add(read_monotonic_time())
+            .checked_add(read_monotonic_time() + sleep_time())
             .unwrap()
     }
 
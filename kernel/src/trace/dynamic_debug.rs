@@ -0,0 +1,44 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The control interface of dynamic debug.
+//!
+//! The rules of dynamic debug can be given at boot with the `dyndbg` kernel parameter (e.g.,
+//! `dyndbg="module aster_kernel::net +p"`) and be queried or changed at runtime via the
+//! `control` file in `/sys/kernel/debug/dynamic_debug`.
+
+use aster_logger::dynamic_debug;
+use spin::Once;
+
+use crate::{
+    fs::debugfs::{self, DebugDir, DebugFile},
+    prelude::*,
+};
+
+static DYNDBG: Once<String> = Once::new();
+aster_cmdline::define_kv_param!("dyndbg", DYNDBG);
+
+pub(super) fn init() {
+    let Some(queries) = DYNDBG.get() else {
+        return;
+    };
+
+    if let Err(err) = dynamic_debug::apply_queries(queries.trim_matches('"')) {
+        warn!("invalid dynamic debug queries '{}': {:?}", queries, err);
+    }
+}
+
+pub(super) fn init_in_first_kthread() {
+    let control = DebugFile::new("control", || {
+        let mut rules = String::new();
+        let _ = dynamic_debug::write_rules(&mut rules);
+        rules
+    })
+    .with_store(|queries| {
+        dynamic_debug::apply_queries(queries)
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the dynamic debug query is invalid"))
+    });
+
+    if let Err(err) = debugfs::register_dir(DebugDir::new("dynamic_debug", vec![control])) {
+        warn!("failed to register dynamic debug in debugfs: {:?}", err);
+    }
+}
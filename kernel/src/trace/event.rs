@@ -0,0 +1,160 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::{
+    fmt::{self, Write},
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+};
+
+use super::{current_tid, is_tracing_on, ring_buffer::trace_buffer};
+
+/// The maximum number of the arguments of a tracepoint.
+pub(crate) const MAX_TRACE_ARGS: usize = 7;
+
+/// An event that is recorded when its tracepoint fires.
+///
+/// The events are defined with [`define_tracepoint`](super::macros::define_tracepoint).
+pub(crate) struct TraceEvent {
+    system: &'static str,
+    name: &'static str,
+    /// The names and the types of the arguments.
+    fields: &'static [(&'static str, &'static str)],
+    /// The format string that [`Self::format`] uses.
+    print_fmt: &'static str,
+    format: fn(&[u64], &mut dyn Write) -> fmt::Result,
+    is_enabled: AtomicBool,
+    /// The index of the event in [`ALL_EVENTS`](super::events::ALL_EVENTS).
+    id: AtomicU32,
+}
+
+impl TraceEvent {
+    pub(super) const fn new(
+        system: &'static str,
+        name: &'static str,
+        fields: &'static [(&'static str, &'static str)],
+        print_fmt: &'static str,
+        format: fn(&[u64], &mut dyn Write) -> fmt::Result,
+    ) -> Self {
+        Self {
+            system,
+            name,
+            fields,
+            print_fmt,
+            format,
+            is_enabled: AtomicBool::new(false),
+            id: AtomicU32::new(u32::MAX),
+        }
+    }
+
+    /// Returns the name of the subsystem that the event belongs to.
+    pub(crate) fn system(&self) -> &'static str {
+        self.system
+    }
+
+    /// Returns the name of the event.
+    pub(crate) fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the names and the types of the arguments.
+    pub(crate) fn fields(&self) -> &'static [(&'static str, &'static str)] {
+        self.fields
+    }
+
+    /// Returns the format string of the event.
+    pub(crate) fn print_fmt(&self) -> &'static str {
+        self.print_fmt
+    }
+
+    /// Returns the ID of the event.
+    pub(crate) fn id(&self) -> u32 {
+        self.id.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn set_id(&self, id: u32) {
+        self.id.store(id, Ordering::Relaxed);
+    }
+
+    /// Returns whether the tracepoint is enabled.
+    #[inline]
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.is_enabled.load(Ordering::Relaxed)
+    }
+
+    /// Enables or disables the tracepoint.
+    pub(crate) fn set_enabled(&self, is_enabled: bool) {
+        self.is_enabled.store(is_enabled, Ordering::Relaxed);
+    }
+
+    /// Records the event with the raw arguments into the trace buffer of the current CPU.
+    pub(super) fn record(&self, args: &[u64]) {
+        if !is_tracing_on() {
+            return;
+        }
+
+        let ts = aster_time::read_monotonic_time().as_nanos() as u64;
+        trace_buffer().record(ts, self.id(), current_tid(), args);
+    }
+
+    /// Formats the raw arguments of a recorded event.
+    pub(crate) fn format(&self, args: &[u64], writer: &mut dyn Write) -> fmt::Result {
+        (self.format)(args, writer)
+    }
+}
+
+/// A type that can be an argument of a tracepoint.
+///
+/// The arguments are stored in the trace buffer as raw `u64`s and are converted back to their
+/// types when they are formatted.
+pub(crate) trait TraceArg: Sized {
+    fn to_raw(self) -> u64;
+
+    fn from_raw(raw: u64) -> Self;
+}
+
+macro_rules! impl_trace_arg_for_int {
+    ($($ty:ty),*) => {
+        $(
+            impl TraceArg for $ty {
+                fn to_raw(self) -> u64 {
+                    self as u64
+                }
+
+                fn from_raw(raw: u64) -> Self {
+                    raw as Self
+                }
+            }
+        )*
+    };
+}
+
+impl_trace_arg_for_int!(u8, u16, u32, usize, i32, i64, isize);
+
+impl TraceArg for u64 {
+    fn to_raw(self) -> u64 {
+        self
+    }
+
+    fn from_raw(raw: u64) -> Self {
+        raw
+    }
+}
+
+impl TraceArg for bool {
+    fn to_raw(self) -> u64 {
+        self as u64
+    }
+
+    fn from_raw(raw: u64) -> Self {
+        raw != 0
+    }
+}
+
+impl TraceArg for char {
+    fn to_raw(self) -> u64 {
+        self as u64
+    }
+
+    fn from_raw(raw: u64) -> Self {
+        char::from_u32(raw as u32).unwrap_or(char::REPLACEMENT_CHARACTER)
+    }
+}
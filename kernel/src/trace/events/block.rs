@@ -0,0 +1,13 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The tracepoints of the block layer.
+
+use crate::trace::macros::define_tracepoint;
+
+define_tracepoint! {
+    /// Fires when a bio is submitted to a block device.
+    ///
+    /// The type of the bio is `R` (read), `W` (write), `F` (flush), or `D` (discard).
+    block: block_bio_queue(major: u16, minor: u32, rwbs: char, sector: u64, nr_sector: u64),
+    "{},{} {} {} + {}"
+}
@@ -0,0 +1,22 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The tracepoints of the subsystems.
+
+pub(crate) mod block;
+pub(crate) mod net;
+pub(crate) mod raw_syscalls;
+pub(crate) mod sched;
+
+use super::TraceEvent;
+
+/// All the trace events.
+///
+/// The ID of an event is its index in this slice.
+pub(crate) static ALL_EVENTS: &[&TraceEvent] = &[
+    &sched::SCHED_SWITCH,
+    &raw_syscalls::SYS_ENTER,
+    &raw_syscalls::SYS_EXIT,
+    &block::BLOCK_BIO_QUEUE,
+    &net::NETIF_RECEIVE_SKB,
+    &net::NET_DEV_XMIT,
+];
@@ -0,0 +1,43 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The tracepoints of the network stack.
+
+use aster_bigtcp::wire::Ipv4Address;
+
+use crate::trace::{TraceArg, macros::define_tracepoint};
+
+define_tracepoint! {
+    /// Fires when an IPv4 packet is received on an iface.
+    net: netif_receive_skb(
+        ifindex: u32,
+        protocol: u8,
+        saddr: Ipv4Address,
+        sport: u16,
+        daddr: Ipv4Address,
+        dport: u16,
+    ),
+    "ifindex={} protocol={} saddr={}:{} daddr={}:{}"
+}
+
+define_tracepoint! {
+    /// Fires when an IPv4 packet is about to be sent on an iface.
+    net: net_dev_xmit(
+        ifindex: u32,
+        protocol: u8,
+        saddr: Ipv4Address,
+        sport: u16,
+        daddr: Ipv4Address,
+        dport: u16,
+    ),
+    "ifindex={} protocol={} saddr={}:{} daddr={}:{}"
+}
+
+impl TraceArg for Ipv4Address {
+    fn to_raw(self) -> u64 {
+        self.to_bits() as u64
+    }
+
+    fn from_raw(raw: u64) -> Self {
+        Ipv4Address::from_bits(raw as u32)
+    }
+}
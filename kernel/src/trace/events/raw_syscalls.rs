@@ -0,0 +1,25 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The tracepoints of system calls.
+
+use crate::trace::macros::define_tracepoint;
+
+define_tracepoint! {
+    /// Fires when a system call is entered.
+    raw_syscalls: sys_enter(
+        id: u64,
+        arg0: u64,
+        arg1: u64,
+        arg2: u64,
+        arg3: u64,
+        arg4: u64,
+        arg5: u64,
+    ),
+    "NR {} ({:x}, {:x}, {:x}, {:x}, {:x}, {:x})"
+}
+
+define_tracepoint! {
+    /// Fires when a system call returns.
+    raw_syscalls: sys_exit(id: u64, ret: i64),
+    "NR {} = {}"
+}
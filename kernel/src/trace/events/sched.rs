@@ -0,0 +1,14 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The tracepoints of the scheduler.
+
+use crate::trace::macros::define_tracepoint;
+
+define_tracepoint! {
+    /// Fires when a CPU switches from the current task to the next task.
+    ///
+    /// The state of the previous task is `R` if it is still runnable, or `S` if it is waiting
+    /// or exiting.
+    sched: sched_switch(prev_pid: u32, prev_state: char, next_pid: u32),
+    "prev_pid={} prev_state={} next_pid={}"
+}
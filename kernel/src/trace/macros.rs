@@ -0,0 +1,64 @@
+// SPDX-License-Identifier: MPL-2.0
+
+/// Defines a tracepoint.
+///
+/// The macro defines a static [`TraceEvent`](super::TraceEvent) named after the event in upper
+/// case, and a `trace_<event>` function that records the event if the tracepoint is enabled.
+/// The arguments are formatted with the format string when the event is read.
+///
+/// # Examples
+///
+/// ```ignore
+/// define_tracepoint! {
+///     /// Fires when a CPU switches from one task to another.
+///     sched: sched_switch(prev_pid: u32, prev_state: char, next_pid: u32),
+///     "prev_pid={} prev_state={} next_pid={}"
+/// }
+///
+/// trace_sched_switch(prev_pid, 'R', next_pid);
+/// ```
+macro_rules! define_tracepoint {
+    (
+        $(#[$attr:meta])*
+        $system:ident: $name:ident($($field:ident: $ty:ty),* $(,)?),
+        $fmt:literal $(,)?
+    ) => {
+        paste::paste! {
+            pub(crate) static [<$name:upper>]: crate::trace::TraceEvent =
+                crate::trace::TraceEvent::new(
+                    stringify!($system),
+                    stringify!($name),
+                    &[$((stringify!($field), stringify!($ty))),*],
+                    $fmt,
+                    [<format_ $name>],
+                );
+
+            const _: () = assert!(
+                <[&str]>::len(&[$(stringify!($field)),*]) <= crate::trace::event::MAX_TRACE_ARGS
+            );
+
+            $(#[$attr])*
+            #[inline]
+            pub(crate) fn [<trace_ $name>]($($field: $ty),*) {
+                if [<$name:upper>].is_enabled() {
+                    [<$name:upper>].record(&[$(crate::trace::TraceArg::to_raw($field)),*]);
+                }
+            }
+
+            fn [<format_ $name>](
+                args: &[u64],
+                writer: &mut dyn core::fmt::Write,
+            ) -> core::fmt::Result {
+                let mut args = args.iter().copied();
+                $(
+                    let $field = <$ty as crate::trace::TraceArg>::from_raw(
+                        args.next().unwrap_or_default(),
+                    );
+                )*
+                write!(writer, $fmt, $($field),*)
+            }
+        }
+    };
+}
+
+pub(super) use define_tracepoint;
@@ -0,0 +1,73 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Static tracepoints.
+//!
+//! A tracepoint is a point in the kernel where an event can be recorded. Tracepoints are defined
+//! with [`define_tracepoint`](macros::define_tracepoint) and grouped by subsystems in the
+//! [`events`] module. Each tracepoint is disabled by default, in which case firing it costs no
+//! more than loading an atomic flag.
+//!
+//! The events of the enabled tracepoints are recorded into per-CPU ring buffers, which can be
+//! read from the `trace` and `trace_pipe` files of tracefs. Tracefs also has an `enable` file
+//! for every event, which turns the tracepoint on and off.
+//!
+//! Like Linux, the recorded events are formatted only when they are read, so recording an event
+//! is merely copying its arguments into the ring buffer.
+//!
+//! This module also hosts the control interface of dynamic debug, which turns on verbose logging
+//! of selected modules at runtime.
+
+mod dynamic_debug;
+mod event;
+pub(crate) mod events;
+mod macros;
+mod ring_buffer;
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use ostd::task::Task;
+
+pub(crate) use self::{
+    event::{TraceArg, TraceEvent},
+    ring_buffer::trace_buffer,
+};
+use crate::process::posix_thread::AsPosixThread;
+
+/// Whether the recording of the events is turned on.
+///
+/// This corresponds to the `tracing_on` file of tracefs. Turning it off keeps the tracepoints
+/// enabled but stops recording their events.
+static TRACING_ON: AtomicBool = AtomicBool::new(true);
+
+pub(super) fn init() {
+    for (id, event) in events::ALL_EVENTS.iter().enumerate() {
+        event.set_id(id as u32);
+    }
+    ring_buffer::init();
+    dynamic_debug::init();
+}
+
+pub(super) fn init_in_first_kthread() {
+    dynamic_debug::init_in_first_kthread();
+}
+
+/// Returns whether the recording of the events is turned on.
+pub(crate) fn is_tracing_on() -> bool {
+    TRACING_ON.load(Ordering::Relaxed)
+}
+
+/// Turns the recording of the events on or off.
+pub(crate) fn set_tracing_on(is_on: bool) {
+    TRACING_ON.store(is_on, Ordering::Relaxed);
+}
+
+/// Returns the thread ID of the task, or zero if the task is not a POSIX thread.
+pub(crate) fn task_tid(task: &Task) -> u32 {
+    task.as_posix_thread()
+        .map_or(0, |posix_thread| posix_thread.tid())
+}
+
+/// Returns the thread ID of the current task, or zero if there is none.
+pub(crate) fn current_tid() -> u32 {
+    Task::current().map_or(0, |task| task_tid(&task))
+}
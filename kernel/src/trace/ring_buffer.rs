@@ -0,0 +1,263 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The per-CPU ring buffers of the trace events.
+//!
+//! Each CPU has its own ring buffer, which is written only by the tracepoints that fire on the
+//! CPU with the local IRQs disabled. So a ring buffer has a single producer, and recording an
+//! event needs no locks. The consumers are serialized by a mutex, and they merge the events of
+//! all CPUs by their timestamps.
+//!
+//! When a ring buffer is full, the new events are dropped (rather than overwriting the old
+//! ones) and counted as overruns.
+
+use alloc::boxed::Box;
+use core::{
+    fmt::Display,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+};
+
+use ostd::{
+    cpu::{PinCurrentCpu, num_cpus},
+    irq::disable_local,
+    util::id_set::Id,
+};
+use spin::Once;
+
+use super::{event::MAX_TRACE_ARGS, events::ALL_EVENTS};
+use crate::prelude::*;
+
+/// The number of the events that the ring buffer of a CPU can hold.
+const NR_RECORDS_PER_CPU: usize = 1024;
+
+/// The number of the words in a record: the timestamp, the event ID and the thread ID, and the
+/// arguments.
+const RECORD_WORDS: usize = 2 + MAX_TRACE_ARGS;
+
+static TRACE_BUFFER: Once<TraceBuffer> = Once::new();
+
+pub(super) fn init() {
+    TRACE_BUFFER.call_once(TraceBuffer::new);
+}
+
+/// Returns the trace buffer.
+pub(crate) fn trace_buffer() -> &'static TraceBuffer {
+    TRACE_BUFFER.get().unwrap()
+}
+
+/// The ring buffers of all CPUs.
+pub(crate) struct TraceBuffer {
+    cpu_buffers: Box<[CpuBuffer]>,
+    /// The lock that serializes the consumers.
+    consumer_lock: Mutex<()>,
+}
+
+/// A recorded event.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TraceRecord {
+    /// The monotonic time in nanoseconds when the event is recorded.
+    pub(crate) ts: u64,
+    pub(crate) cpu: u32,
+    pub(crate) event_id: u32,
+    /// The thread ID of the task that fires the tracepoint.
+    pub(crate) tid: u32,
+    pub(crate) args: [u64; MAX_TRACE_ARGS],
+}
+
+impl Display for TraceRecord {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let Some(event) = ALL_EVENTS.get(self.event_id as usize) else {
+            return Ok(());
+        };
+
+        // Like Linux, the task name is shown as `<...>` if it is not saved.
+        let comm = if self.tid == 0 { "<idle>" } else { "<...>" };
+        let secs = self.ts / 1_000_000_000;
+        let usecs = self.ts % 1_000_000_000 / 1_000;
+        write!(
+            f,
+            "{:>16}-{:<7} [{:03}] {:5}.{:06}: {}: ",
+            comm,
+            self.tid,
+            self.cpu,
+            secs,
+            usecs,
+            event.name()
+        )?;
+        event.format(&self.args, f)?;
+        writeln!(f)
+    }
+}
+
+impl TraceBuffer {
+    fn new() -> Self {
+        let cpu_buffers = (0..num_cpus()).map(|_| CpuBuffer::new()).collect();
+
+        Self {
+            cpu_buffers,
+            consumer_lock: Mutex::new(()),
+        }
+    }
+
+    /// Records an event into the ring buffer of the current CPU.
+    pub(super) fn record(&self, ts: u64, event_id: u32, tid: u32, args: &[u64]) {
+        let irq_guard = disable_local();
+        let cpu = irq_guard.current_cpu();
+        self.cpu_buffers[cpu.as_usize()].push(ts, event_id, tid, args);
+    }
+
+    /// Visits the recorded events in the order of their timestamps without consuming them.
+    ///
+    /// The visit stops if `visitor` returns `false`.
+    pub(crate) fn for_each(&self, mut visitor: impl FnMut(&TraceRecord) -> bool) {
+        let _guard = self.consumer_lock.lock();
+
+        let mut cursors = self.tails();
+        while let Some((cpu, record)) = self.peek_earliest(&cursors) {
+            cursors[cpu] += 1;
+            if !visitor(&record) {
+                break;
+            }
+        }
+    }
+
+    /// Consumes the recorded events in the order of their timestamps.
+    ///
+    /// An event is consumed only if `consumer` returns `true` for it. Otherwise, the consumption
+    /// stops and the event is kept in the ring buffer.
+    pub(crate) fn consume(&self, mut consumer: impl FnMut(&TraceRecord) -> bool) {
+        let _guard = self.consumer_lock.lock();
+
+        let mut cursors = self.tails();
+        while let Some((cpu, record)) = self.peek_earliest(&cursors) {
+            if !consumer(&record) {
+                break;
+            }
+            cursors[cpu] += 1;
+            self.cpu_buffers[cpu]
+                .tail
+                .store(cursors[cpu], Ordering::Release);
+        }
+    }
+
+    /// Returns whether there are no recorded events.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.cpu_buffers.iter().all(CpuBuffer::is_empty)
+    }
+
+    /// Discards all the recorded events.
+    pub(crate) fn clear(&self) {
+        let _guard = self.consumer_lock.lock();
+
+        for buffer in self.cpu_buffers.iter() {
+            let head = buffer.head.load(Ordering::Acquire);
+            buffer.tail.store(head, Ordering::Release);
+            buffer.overrun.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the number of the events in the ring buffers and the number of the events that
+    /// have been recorded (including the dropped ones) since the last clear.
+    pub(crate) fn stats(&self) -> (usize, usize) {
+        self.cpu_buffers
+            .iter()
+            .fold((0, 0), |(entries, written), buffer| {
+                let tail = buffer.tail.load(Ordering::Acquire);
+                let head = buffer.head.load(Ordering::Acquire);
+                let overrun = buffer.overrun.load(Ordering::Relaxed);
+                (entries + head - tail, written + head - tail + overrun)
+            })
+    }
+
+    fn tails(&self) -> Vec<usize> {
+        self.cpu_buffers
+            .iter()
+            .map(|buffer| buffer.tail.load(Ordering::Relaxed))
+            .collect()
+    }
+
+    /// Returns the earliest event at the cursors and the CPU of the event.
+    fn peek_earliest(&self, cursors: &[usize]) -> Option<(usize, TraceRecord)> {
+        self.cpu_buffers
+            .iter()
+            .zip(cursors)
+            .enumerate()
+            .filter_map(|(cpu, (buffer, cursor))| {
+                let record = buffer.read(*cursor, cpu as u32)?;
+                Some((cpu, record))
+            })
+            .min_by_key(|(_, record)| record.ts)
+    }
+}
+
+/// The ring buffer of a CPU.
+struct CpuBuffer {
+    records: Box<[[AtomicU64; RECORD_WORDS]]>,
+    /// The position where the next event will be written.
+    ///
+    /// Only the producer (i.e., the tracepoints on the CPU) updates it.
+    head: AtomicUsize,
+    /// The position of the earliest event.
+    ///
+    /// Only the consumers update it.
+    tail: AtomicUsize,
+    /// The number of the events that have been dropped because the ring buffer is full.
+    overrun: AtomicUsize,
+}
+
+impl CpuBuffer {
+    fn new() -> Self {
+        let records = (0..NR_RECORDS_PER_CPU)
+            .map(|_| core::array::from_fn(|_| AtomicU64::new(0)))
+            .collect();
+
+        Self {
+            records,
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            overrun: AtomicUsize::new(0),
+        }
+    }
+
+    /// Pushes an event into the ring buffer.
+    ///
+    /// This method must be called on the CPU of the ring buffer with the local IRQs disabled.
+    fn push(&self, ts: u64, event_id: u32, tid: u32, args: &[u64]) {
+        let head = self.head.load(Ordering::Relaxed);
+        let tail = self.tail.load(Ordering::Acquire);
+        if head - tail >= NR_RECORDS_PER_CPU {
+            self.overrun.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        let record = &self.records[head % NR_RECORDS_PER_CPU];
+        record[0].store(ts, Ordering::Relaxed);
+        record[1].store(((event_id as u64) << 32) | tid as u64, Ordering::Relaxed);
+        for (word, arg) in record[2..].iter().zip(args) {
+            word.store(*arg, Ordering::Relaxed);
+        }
+
+        self.head.store(head + 1, Ordering::Release);
+    }
+
+    /// Reads the event at the position, if it has been written and not been consumed.
+    fn read(&self, pos: usize, cpu: u32) -> Option<TraceRecord> {
+        let head = self.head.load(Ordering::Acquire);
+        if pos >= head {
+            return None;
+        }
+
+        let record = &self.records[pos % NR_RECORDS_PER_CPU];
+        let header = record[1].load(Ordering::Relaxed);
+        Some(TraceRecord {
+            ts: record[0].load(Ordering::Relaxed),
+            cpu,
+            event_id: (header >> 32) as u32,
+            tid: header as u32,
+            args: core::array::from_fn(|i| record[2 + i].load(Ordering::Relaxed)),
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.head.load(Ordering::Acquire) == self.tail.load(Ordering::Acquire)
+    }
+}
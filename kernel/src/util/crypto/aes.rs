@@ -0,0 +1,734 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The AES block cipher and its CBC, CTR, XTS, CBC-CTS and GCM modes.
+//!
+//! The block cipher uses the AES instructions (AES-NI) if the processor supports them.
+//!
+//! Reference: <https://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.197-upd1.pdf>
+
+/// The size of an AES block in bytes.
+pub const AES_BLOCK_SIZE: usize = 16;
+
+/// The number of rounds with a 256-bit key, which is the maximum number of rounds.
+const MAX_NR_ROUNDS: usize = 14;
+
+/// The AES block cipher with a 128-bit, a 192-bit or a 256-bit key.
+pub struct Aes {
+    round_keys: [[u8; AES_BLOCK_SIZE]; MAX_NR_ROUNDS + 1],
+    /// The round keys of the equivalent inverse cipher, which are only computed if AES-NI
+    /// is used.
+    #[cfg(target_arch = "x86_64")]
+    inv_round_keys: Option<[[u8; AES_BLOCK_SIZE]; MAX_NR_ROUNDS + 1]>,
+    nr_rounds: usize,
+}
+
+impl Aes {
+    /// Creates a cipher with the 128-bit key.
+    pub fn new_128(key: &[u8; 16]) -> Self {
+        Self::expand_key(key, 10)
+    }
+
+    /// Creates a cipher with the 256-bit key.
+    pub fn new_256(key: &[u8; 32]) -> Self {
+        Self::expand_key(key, MAX_NR_ROUNDS)
+    }
+
+    /// Creates a cipher with the key, whose size determines the key size of the cipher.
+    ///
+    /// This method returns `None` if the key is not 128, 192 or 256 bits long.
+    pub fn new(key: &[u8]) -> Option<Self> {
+        match key.len() {
+            16 => Some(Self::expand_key(key, 10)),
+            24 => Some(Self::expand_key(key, 12)),
+            32 => Some(Self::expand_key(key, MAX_NR_ROUNDS)),
+            _ => None,
+        }
+    }
+
+    fn expand_key(key: &[u8], nr_rounds: usize) -> Self {
+        // The key expansion works on 32-bit words.
+        let nr_key_words = key.len() / 4;
+        let mut words = [[0u8; 4]; 4 * (MAX_NR_ROUNDS + 1)];
+        for (word, chunk) in words.iter_mut().zip(key.chunks_exact(4)) {
+            word.copy_from_slice(chunk);
+        }
+
+        let mut rcon = 1u8;
+        for i in nr_key_words..4 * (nr_rounds + 1) {
+            let mut temp = words[i - 1];
+            if i % nr_key_words == 0 {
+                temp.rotate_left(1);
+                temp = temp.map(|byte| SBOX[byte as usize]);
+                temp[0] ^= rcon;
+                rcon = xtime(rcon);
+            } else if nr_key_words > 6 && i % nr_key_words == 4 {
+                temp = temp.map(|byte| SBOX[byte as usize]);
+            }
+            for (byte, prev) in temp.iter_mut().zip(words[i - nr_key_words]) {
+                *byte ^= prev;
+            }
+            words[i] = temp;
+        }
+
+        let mut round_keys = [[0u8; AES_BLOCK_SIZE]; MAX_NR_ROUNDS + 1];
+        for (round_key, round_words) in round_keys.iter_mut().zip(words.chunks_exact(4)) {
+            for (chunk, word) in round_key.chunks_exact_mut(4).zip(round_words) {
+                chunk.copy_from_slice(word);
+            }
+        }
+
+        #[cfg(target_arch = "x86_64")]
+        let inv_round_keys = ostd::arch::crypto::has_aes().then(|| {
+            let mut inv_round_keys = round_keys;
+            inv_round_keys[..=nr_rounds].reverse();
+            for round_key in &mut inv_round_keys[1..nr_rounds] {
+                inv_mix_columns(round_key);
+            }
+            inv_round_keys
+        });
+
+        Self {
+            round_keys,
+            #[cfg(target_arch = "x86_64")]
+            inv_round_keys,
+            nr_rounds,
+        }
+    }
+
+    /// Encrypts a block in place.
+    pub fn encrypt_block(&self, block: &mut [u8; AES_BLOCK_SIZE]) {
+        #[cfg(target_arch = "x86_64")]
+        if self.inv_round_keys.is_some() {
+            ostd::arch::crypto::aes_encrypt_block(&self.round_keys[..=self.nr_rounds], block);
+            return;
+        }
+
+        xor_in_place(block, &self.round_keys[0]);
+        for round in 1..=self.nr_rounds {
+            for byte in block.iter_mut() {
+                *byte = SBOX[*byte as usize];
+            }
+            shift_rows(block);
+            if round != self.nr_rounds {
+                mix_columns(block);
+            }
+            xor_in_place(block, &self.round_keys[round]);
+        }
+    }
+
+    /// Decrypts a block in place.
+    pub fn decrypt_block(&self, block: &mut [u8; AES_BLOCK_SIZE]) {
+        #[cfg(target_arch = "x86_64")]
+        if let Some(inv_round_keys) = &self.inv_round_keys {
+            ostd::arch::crypto::aes_decrypt_block(&inv_round_keys[..=self.nr_rounds], block);
+            return;
+        }
+
+        xor_in_place(block, &self.round_keys[self.nr_rounds]);
+        for round in (0..self.nr_rounds).rev() {
+            inv_shift_rows(block);
+            for byte in block.iter_mut() {
+                *byte = INV_SBOX[*byte as usize];
+            }
+            xor_in_place(block, &self.round_keys[round]);
+            if round != 0 {
+                inv_mix_columns(block);
+            }
+        }
+    }
+}
+
+/// The AES cipher in the CBC mode.
+///
+/// Reference: <https://nvlpubs.nist.gov/nistpubs/Legacy/SP/nistspecialpublication800-38a.pdf>
+pub struct AesCbc {
+    cipher: Aes,
+}
+
+impl AesCbc {
+    /// Creates a cipher on top of the block cipher.
+    pub fn new(cipher: Aes) -> Self {
+        Self { cipher }
+    }
+
+    /// Encrypts the data in place with the IV.
+    ///
+    /// The length of the data must be a multiple of the block size. The IV is updated to
+    /// the last ciphertext block, so that the next data can be encrypted in a chain.
+    pub fn encrypt(&self, iv: &mut [u8; AES_BLOCK_SIZE], data: &mut [u8]) {
+        debug_assert_eq!(data.len() % AES_BLOCK_SIZE, 0);
+
+        for chunk in data.chunks_exact_mut(AES_BLOCK_SIZE) {
+            let block: &mut [u8; AES_BLOCK_SIZE] = chunk.try_into().unwrap();
+            xor_in_place(block, iv);
+            self.cipher.encrypt_block(block);
+            *iv = *block;
+        }
+    }
+
+    /// Decrypts the data in place with the IV.
+    ///
+    /// The length of the data must be a multiple of the block size. The IV is updated to
+    /// the last ciphertext block, so that the next data can be decrypted in a chain.
+    pub fn decrypt(&self, iv: &mut [u8; AES_BLOCK_SIZE], data: &mut [u8]) {
+        debug_assert_eq!(data.len() % AES_BLOCK_SIZE, 0);
+
+        for chunk in data.chunks_exact_mut(AES_BLOCK_SIZE) {
+            let block: &mut [u8; AES_BLOCK_SIZE] = chunk.try_into().unwrap();
+            let ciphertext = *block;
+            self.cipher.decrypt_block(block);
+            xor_in_place(block, iv);
+            *iv = ciphertext;
+        }
+    }
+}
+
+/// The AES cipher in the CTR mode with a 128-bit big-endian counter.
+///
+/// Reference: <https://nvlpubs.nist.gov/nistpubs/Legacy/SP/nistspecialpublication800-38a.pdf>
+pub struct AesCtr {
+    cipher: Aes,
+}
+
+impl AesCtr {
+    /// Creates a cipher on top of the block cipher.
+    pub fn new(cipher: Aes) -> Self {
+        Self { cipher }
+    }
+
+    /// Encrypts or decrypts the data in place, starting from the counter block.
+    ///
+    /// The counter block is incremented once for each block (including the last partial
+    /// block), so that the next data can be processed in a chain.
+    pub fn apply_keystream(&self, counter: &mut [u8; AES_BLOCK_SIZE], data: &mut [u8]) {
+        for chunk in data.chunks_mut(AES_BLOCK_SIZE) {
+            let mut keystream = *counter;
+            self.cipher.encrypt_block(&mut keystream);
+            for (byte, key) in chunk.iter_mut().zip(keystream) {
+                *byte ^= key;
+            }
+            *counter = u128::from_be_bytes(*counter).wrapping_add(1).to_be_bytes();
+        }
+    }
+}
+
+/// The AES cipher in the XTS mode, which is used to encrypt the data on disks.
+///
+/// The length of the data must be a multiple of the block size, so ciphertext
+/// stealing is not supported.
+///
+/// Reference: <https://standards.ieee.org/ieee/1619/4205/>
+pub struct AesXts {
+    data_cipher: Aes,
+    tweak_cipher: Aes,
+}
+
+impl AesXts {
+    /// Creates a cipher with the key, which consists of the data key and the tweak key.
+    ///
+    /// This method returns `None` if the key is not 256 or 512 bits long, i.e., if it does
+    /// not consist of two AES-128 or two AES-256 keys.
+    pub fn new(key: &[u8]) -> Option<Self> {
+        if key.len() != 32 && key.len() != 64 {
+            return None;
+        }
+        let (data_key, tweak_key) = key.split_at(key.len() / 2);
+        Some(Self {
+            data_cipher: Aes::new(data_key)?,
+            tweak_cipher: Aes::new(tweak_key)?,
+        })
+    }
+
+    /// Encrypts the data in place with the IV.
+    pub fn encrypt(&self, iv: &[u8; AES_BLOCK_SIZE], data: &mut [u8]) {
+        self.process(iv, data, |cipher, block| cipher.encrypt_block(block));
+    }
+
+    /// Decrypts the data in place with the IV.
+    pub fn decrypt(&self, iv: &[u8; AES_BLOCK_SIZE], data: &mut [u8]) {
+        self.process(iv, data, |cipher, block| cipher.decrypt_block(block));
+    }
+
+    fn process(
+        &self,
+        iv: &[u8; AES_BLOCK_SIZE],
+        data: &mut [u8],
+        op: impl Fn(&Aes, &mut [u8; AES_BLOCK_SIZE]),
+    ) {
+        debug_assert_eq!(data.len() % AES_BLOCK_SIZE, 0);
+
+        let mut tweak = *iv;
+        self.tweak_cipher.encrypt_block(&mut tweak);
+        for chunk in data.chunks_exact_mut(AES_BLOCK_SIZE) {
+            let block: &mut [u8; AES_BLOCK_SIZE] = chunk.try_into().unwrap();
+            xor_in_place(block, &tweak);
+            op(&self.data_cipher, block);
+            xor_in_place(block, &tweak);
+            gf128_mul_x(&mut tweak);
+        }
+    }
+}
+
+/// The AES cipher in the CBC mode with ciphertext stealing, which is used to encrypt the
+/// file names.
+///
+/// The last two blocks are always swapped if there are more than one block, which
+/// is the CS3 variant.
+///
+/// Reference: <https://nvlpubs.nist.gov/nistpubs/Legacy/SP/nist-special-publication-800-38a-add.pdf>
+pub struct AesCbcCts {
+    cipher: Aes,
+}
+
+impl AesCbcCts {
+    /// Creates a cipher on top of the block cipher.
+    pub fn new(cipher: Aes) -> Self {
+        Self { cipher }
+    }
+
+    /// Encrypts the data in place with the IV.
+    ///
+    /// The data must be at least one block long.
+    pub fn encrypt(&self, iv: &[u8; AES_BLOCK_SIZE], data: &mut [u8]) {
+        debug_assert!(data.len() >= AES_BLOCK_SIZE);
+
+        let (full_len, last_len) = split_last_two_blocks(data.len());
+        let mut prev = *iv;
+        for chunk in data[..full_len].chunks_exact_mut(AES_BLOCK_SIZE) {
+            let block: &mut [u8; AES_BLOCK_SIZE] = chunk.try_into().unwrap();
+            xor_in_place(block, &prev);
+            self.cipher.encrypt_block(block);
+            prev = *block;
+        }
+        if last_len == 0 {
+            return;
+        }
+
+        // Encrypts the zero-padded last block, which is stored before the stolen
+        // ciphertext of the second-to-last block.
+        let second_last_start = full_len - AES_BLOCK_SIZE;
+        let second_last: [u8; AES_BLOCK_SIZE] =
+            data[second_last_start..full_len].try_into().unwrap();
+        let mut last = [0u8; AES_BLOCK_SIZE];
+        last[..last_len].copy_from_slice(&data[full_len..]);
+        xor_in_place(&mut last, &second_last);
+        self.cipher.encrypt_block(&mut last);
+
+        data[second_last_start..full_len].copy_from_slice(&last);
+        data[full_len..].copy_from_slice(&second_last[..last_len]);
+    }
+
+    /// Decrypts the data in place with the IV.
+    ///
+    /// The data must be at least one block long.
+    pub fn decrypt(&self, iv: &[u8; AES_BLOCK_SIZE], data: &mut [u8]) {
+        debug_assert!(data.len() >= AES_BLOCK_SIZE);
+
+        let (full_len, last_len) = split_last_two_blocks(data.len());
+        if last_len != 0 {
+            // Recovers the second-to-last block of the ciphertext, whose tail is the
+            // tail of the decrypted last block, since the last block is zero-padded.
+            let second_last_start = full_len - AES_BLOCK_SIZE;
+            let mut last: [u8; AES_BLOCK_SIZE] =
+                data[second_last_start..full_len].try_into().unwrap();
+            self.cipher.decrypt_block(&mut last);
+            let mut second_last = last;
+            second_last[..last_len].copy_from_slice(&data[full_len..]);
+            xor_in_place(&mut last, &second_last);
+
+            data[second_last_start..full_len].copy_from_slice(&second_last);
+            data[full_len..].copy_from_slice(&last[..last_len]);
+        }
+
+        let mut prev = *iv;
+        for chunk in data[..full_len].chunks_exact_mut(AES_BLOCK_SIZE) {
+            let block: &mut [u8; AES_BLOCK_SIZE] = chunk.try_into().unwrap();
+            let ciphertext = *block;
+            self.cipher.decrypt_block(block);
+            xor_in_place(block, &prev);
+            prev = ciphertext;
+        }
+    }
+}
+
+/// The size of an AES-GCM nonce in bytes.
+pub const AES_GCM_NONCE_SIZE: usize = 12;
+
+/// The size of an AES-GCM authentication tag in bytes.
+pub const AES_GCM_TAG_SIZE: usize = 16;
+
+/// The AES cipher in the Galois/Counter Mode (GCM), which is an AEAD construction.
+///
+/// Only 96-bit nonces are supported, which is what the protocols use in practice.
+///
+/// Reference: <https://nvlpubs.nist.gov/nistpubs/Legacy/SP/nistspecialpublication800-38d.pdf>
+pub struct AesGcm {
+    cipher: Aes,
+    /// The hash subkey, which is the encryption of the zero block.
+    hash_key: u128,
+}
+
+impl AesGcm {
+    /// Creates a cipher on top of the block cipher.
+    pub fn new(cipher: Aes) -> Self {
+        let mut block = [0u8; AES_BLOCK_SIZE];
+        cipher.encrypt_block(&mut block);
+        Self {
+            cipher,
+            hash_key: u128::from_be_bytes(block),
+        }
+    }
+
+    /// Encrypts the data in place and returns the authentication tag.
+    ///
+    /// The tag also authenticates the additional data, which is not encrypted.
+    pub fn seal(
+        &self,
+        nonce: &[u8; AES_GCM_NONCE_SIZE],
+        aad: &[u8],
+        data: &mut [u8],
+    ) -> [u8; AES_GCM_TAG_SIZE] {
+        self.apply_keystream(nonce, data);
+        self.compute_tag(nonce, aad, data)
+    }
+
+    /// Verifies the authentication tag and decrypts the data in place.
+    ///
+    /// The tag may be truncated, in which case only its leading bytes are verified.
+    ///
+    /// This method returns `false` and leaves the data unchanged if the tag is invalid.
+    #[must_use]
+    pub fn open(
+        &self,
+        nonce: &[u8; AES_GCM_NONCE_SIZE],
+        aad: &[u8],
+        data: &mut [u8],
+        tag: &[u8],
+    ) -> bool {
+        if tag.is_empty() || tag.len() > AES_GCM_TAG_SIZE {
+            return false;
+        }
+
+        let expected_tag = self.compute_tag(nonce, aad, data);
+
+        // Compare the tags in constant time.
+        let diff = expected_tag
+            .iter()
+            .zip(tag)
+            .fold(0u8, |diff, (lhs, rhs)| diff | (lhs ^ rhs));
+        if diff != 0 {
+            return false;
+        }
+
+        self.apply_keystream(nonce, data);
+        true
+    }
+
+    fn apply_keystream(&self, nonce: &[u8; AES_GCM_NONCE_SIZE], data: &mut [u8]) {
+        // The first counter block is reserved for the tag.
+        for (chunk, counter) in data.chunks_mut(AES_BLOCK_SIZE).zip(2u32..) {
+            let mut keystream = counter_block(nonce, counter);
+            self.cipher.encrypt_block(&mut keystream);
+            for (byte, key) in chunk.iter_mut().zip(keystream) {
+                *byte ^= key;
+            }
+        }
+    }
+
+    fn compute_tag(
+        &self,
+        nonce: &[u8; AES_GCM_NONCE_SIZE],
+        aad: &[u8],
+        ciphertext: &[u8],
+    ) -> [u8; AES_GCM_TAG_SIZE] {
+        let mut hash = 0u128;
+        for data in [aad, ciphertext] {
+            for chunk in data.chunks(AES_BLOCK_SIZE) {
+                let mut block = [0u8; AES_BLOCK_SIZE];
+                block[..chunk.len()].copy_from_slice(chunk);
+                hash = ghash_mul(hash ^ u128::from_be_bytes(block), self.hash_key);
+            }
+        }
+        let bit_lens = ((aad.len() as u128 * 8) << 64) | (ciphertext.len() as u128 * 8);
+        hash = ghash_mul(hash ^ bit_lens, self.hash_key);
+
+        let mut tag = counter_block(nonce, 1);
+        self.cipher.encrypt_block(&mut tag);
+        (u128::from_be_bytes(tag) ^ hash).to_be_bytes()
+    }
+}
+
+fn counter_block(nonce: &[u8; AES_GCM_NONCE_SIZE], counter: u32) -> [u8; AES_BLOCK_SIZE] {
+    let mut block = [0u8; AES_BLOCK_SIZE];
+    block[..AES_GCM_NONCE_SIZE].copy_from_slice(nonce);
+    block[AES_GCM_NONCE_SIZE..].copy_from_slice(&counter.to_be_bytes());
+    block
+}
+
+/// Multiplies two elements in GF(2^128), where the elements are big-endian and the bits are
+/// reflected, as in GCM.
+///
+/// The multiplication runs in constant time.
+fn ghash_mul(x: u128, y: u128) -> u128 {
+    const R: u128 = 0xe1 << 120;
+
+    let mut product = 0;
+    let mut v = y;
+    for i in (0..128).rev() {
+        product ^= v & 0u128.wrapping_sub((x >> i) & 1);
+        v = (v >> 1) ^ (R & 0u128.wrapping_sub(v & 1));
+    }
+    product
+}
+
+/// Splits the length into the length of the full blocks and the length of the partial
+/// last block, where the last block is treated as partial if there are more than one
+/// block.
+fn split_last_two_blocks(len: usize) -> (usize, usize) {
+    if len <= AES_BLOCK_SIZE {
+        return (len, 0);
+    }
+    let last_len = match len % AES_BLOCK_SIZE {
+        0 => AES_BLOCK_SIZE,
+        rem => rem,
+    };
+    (len - last_len, last_len)
+}
+
+fn xor_in_place(block: &mut [u8; AES_BLOCK_SIZE], other: &[u8; AES_BLOCK_SIZE]) {
+    for (byte, other) in block.iter_mut().zip(other) {
+        *byte ^= other;
+    }
+}
+
+/// Multiplies the tweak by `x` in GF(2^128), where the tweak is little-endian.
+fn gf128_mul_x(tweak: &mut [u8; AES_BLOCK_SIZE]) {
+    let mut carry = 0;
+    for byte in tweak.iter_mut() {
+        let next_carry = *byte >> 7;
+        *byte = (*byte << 1) | carry;
+        carry = next_carry;
+    }
+    if carry != 0 {
+        tweak[0] ^= 0x87;
+    }
+}
+
+const fn xtime(byte: u8) -> u8 {
+    (byte << 1) ^ if byte & 0x80 != 0 { 0x1b } else { 0 }
+}
+
+const fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        a = xtime(a);
+        b >>= 1;
+    }
+    product
+}
+
+// The state is stored in the column-major order, as in the standard.
+
+fn shift_rows(block: &mut [u8; AES_BLOCK_SIZE]) {
+    let state = *block;
+    for col in 0..4 {
+        for row in 0..4 {
+            block[col * 4 + row] = state[((col + row) % 4) * 4 + row];
+        }
+    }
+}
+
+fn inv_shift_rows(block: &mut [u8; AES_BLOCK_SIZE]) {
+    let state = *block;
+    for col in 0..4 {
+        for row in 0..4 {
+            block[((col + row) % 4) * 4 + row] = state[col * 4 + row];
+        }
+    }
+}
+
+fn mix_columns(block: &mut [u8; AES_BLOCK_SIZE]) {
+    for col in block.chunks_exact_mut(4) {
+        let [a0, a1, a2, a3] = [col[0], col[1], col[2], col[3]];
+        col[0] = xtime(a0) ^ (xtime(a1) ^ a1) ^ a2 ^ a3;
+        col[1] = a0 ^ xtime(a1) ^ (xtime(a2) ^ a2) ^ a3;
+        col[2] = a0 ^ a1 ^ xtime(a2) ^ (xtime(a3) ^ a3);
+        col[3] = (xtime(a0) ^ a0) ^ a1 ^ a2 ^ xtime(a3);
+    }
+}
+
+fn inv_mix_columns(block: &mut [u8; AES_BLOCK_SIZE]) {
+    for col in block.chunks_exact_mut(4) {
+        let [a0, a1, a2, a3] = [col[0], col[1], col[2], col[3]];
+        col[0] = gf_mul(a0, 14) ^ gf_mul(a1, 11) ^ gf_mul(a2, 13) ^ gf_mul(a3, 9);
+        col[1] = gf_mul(a0, 9) ^ gf_mul(a1, 14) ^ gf_mul(a2, 11) ^ gf_mul(a3, 13);
+        col[2] = gf_mul(a0, 13) ^ gf_mul(a1, 9) ^ gf_mul(a2, 14) ^ gf_mul(a3, 11);
+        col[3] = gf_mul(a0, 11) ^ gf_mul(a1, 13) ^ gf_mul(a2, 9) ^ gf_mul(a3, 14);
+    }
+}
+
+const SBOX: [u8; 256] = {
+    let mut sbox = [0u8; 256];
+    // Walks through the multiplicative group with the generator 3, keeping `q` as the
+    // inverse of `p`.
+    let mut p = 1u8;
+    let mut q = 1u8;
+    loop {
+        // Multiplies `p` by 3.
+        p ^= xtime(p);
+        // Divides `q` by 3.
+        q ^= q << 1;
+        q ^= q << 2;
+        q ^= q << 4;
+        if q & 0x80 != 0 {
+            q ^= 0x09;
+        }
+        let affine =
+            q ^ q.rotate_left(1) ^ q.rotate_left(2) ^ q.rotate_left(3) ^ q.rotate_left(4) ^ 0x63;
+        sbox[p as usize] = affine;
+        if p == 1 {
+            break;
+        }
+    }
+    // Zero has no inverse.
+    sbox[0] = 0x63;
+    sbox
+};
+
+const INV_SBOX: [u8; 256] = {
+    let mut inv_sbox = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        inv_sbox[SBOX[i] as usize] = i as u8;
+        i += 1;
+    }
+    inv_sbox
+};
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    fn from_hex<const N: usize>(hex: &str) -> [u8; N] {
+        let mut bytes = [0u8; N];
+        for (byte, chunk) in bytes.iter_mut().zip(hex.as_bytes().chunks_exact(2)) {
+            *byte = u8::from_str_radix(core::str::from_utf8(chunk).unwrap(), 16).unwrap();
+        }
+        bytes
+    }
+
+    #[ktest]
+    fn aes256_fips197() {
+        let key =
+            from_hex::<32>("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f");
+        let cipher = Aes::new_256(&key);
+        let mut block = from_hex::<16>("00112233445566778899aabbccddeeff");
+        cipher.encrypt_block(&mut block);
+        assert_eq!(block, from_hex::<16>("8ea2b7ca516745bfeafc49904b496089"));
+        cipher.decrypt_block(&mut block);
+        assert_eq!(block, from_hex::<16>("00112233445566778899aabbccddeeff"));
+    }
+
+    #[ktest]
+    fn aes128_fips197() {
+        let key = from_hex::<16>("000102030405060708090a0b0c0d0e0f");
+        let cipher = Aes::new_128(&key);
+        let mut block = from_hex::<16>("00112233445566778899aabbccddeeff");
+        cipher.encrypt_block(&mut block);
+        assert_eq!(block, from_hex::<16>("69c4e0d86a7b0430d8cdb78070b4c55a"));
+        cipher.decrypt_block(&mut block);
+        assert_eq!(block, from_hex::<16>("00112233445566778899aabbccddeeff"));
+    }
+
+    #[ktest]
+    fn aes192_fips197() {
+        let key = from_hex::<24>("000102030405060708090a0b0c0d0e0f1011121314151617");
+        let cipher = Aes::new(&key).unwrap();
+        let mut block = from_hex::<16>("00112233445566778899aabbccddeeff");
+        cipher.encrypt_block(&mut block);
+        assert_eq!(block, from_hex::<16>("dda97ca4864cdfe06eaf70a0ec0d7191"));
+        cipher.decrypt_block(&mut block);
+        assert_eq!(block, from_hex::<16>("00112233445566778899aabbccddeeff"));
+    }
+
+    // The test vectors are from Appendix F of NIST SP 800-38A.
+    const SP800_38A_KEY: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+    const SP800_38A_PLAINTEXT: &str = concat!(
+        "6bc1bee22e409f96e93d7e117393172a",
+        "ae2d8a571e03ac9c9eb76fac45af8e51",
+    );
+
+    #[ktest]
+    fn aes128_cbc() {
+        let cipher = AesCbc::new(Aes::new_128(&from_hex(SP800_38A_KEY)));
+        let plaintext = from_hex::<32>(SP800_38A_PLAINTEXT);
+        let ciphertext = from_hex::<32>(concat!(
+            "7649abac8119b246cee98e9b12e9197d",
+            "5086cb9b507219ee95db113a917678b2",
+        ));
+
+        // Encrypt the blocks one by one to check the chaining of the IV.
+        let mut iv = from_hex("000102030405060708090a0b0c0d0e0f");
+        let mut data = plaintext;
+        cipher.encrypt(&mut iv, &mut data[..16]);
+        cipher.encrypt(&mut iv, &mut data[16..]);
+        assert_eq!(data, ciphertext);
+
+        let mut iv = from_hex("000102030405060708090a0b0c0d0e0f");
+        cipher.decrypt(&mut iv, &mut data);
+        assert_eq!(data, plaintext);
+    }
+
+    #[ktest]
+    fn aes128_ctr() {
+        let cipher = AesCtr::new(Aes::new_128(&from_hex(SP800_38A_KEY)));
+        let ciphertext = from_hex::<32>(concat!(
+            "874d6191b620e3261bef6864990db6ce",
+            "9806f66b7970fdff8617187bb9fffdff",
+        ));
+
+        let mut counter = from_hex("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff");
+        let mut data = from_hex::<32>(SP800_38A_PLAINTEXT);
+        cipher.apply_keystream(&mut counter, &mut data);
+        assert_eq!(data, ciphertext);
+        assert_eq!(counter, from_hex::<16>("f0f1f2f3f4f5f6f7f8f9fafbfcfdff01"));
+    }
+
+    #[ktest]
+    fn aes128_gcm() {
+        // Test case 4 in the GCM specification.
+        let cipher = AesGcm::new(Aes::new_128(&from_hex("feffe9928665731c6d6a8f9467308308")));
+        let nonce = from_hex("cafebabefacedbaddecaf888");
+        let aad = from_hex::<20>("feedfacedeadbeeffeedfacedeadbeefabaddad2");
+        let plaintext = from_hex::<60>(concat!(
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72",
+            "1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+        ));
+        let ciphertext = from_hex::<60>(concat!(
+            "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e",
+            "21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091",
+        ));
+        let tag = from_hex::<16>("5bc94fbc3221a5db94fae95ae7121a47");
+
+        let mut data = plaintext;
+        assert_eq!(cipher.seal(&nonce, &aad, &mut data), tag);
+        assert_eq!(data, ciphertext);
+
+        let mut bad_tag = tag;
+        bad_tag[0] ^= 1;
+        assert!(!cipher.open(&nonce, &aad, &mut data, &bad_tag));
+        assert_eq!(data, ciphertext);
+        assert!(cipher.open(&nonce, &aad, &mut data, &tag));
+        assert_eq!(data, plaintext);
+
+        // Truncated tags are verified by their leading bytes.
+        cipher.seal(&nonce, &aad, &mut data);
+        assert!(cipher.open(&nonce, &aad, &mut data, &tag[..12]));
+        assert_eq!(data, plaintext);
+    }
+}
@@ -0,0 +1,123 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    prelude::*,
+    util::crypto::{
+        aes::{AES_GCM_NONCE_SIZE, AES_GCM_TAG_SIZE, Aes, AesGcm},
+        chacha20poly1305::{
+            CHACHA20_POLY1305_KEY_SIZE, CHACHA20_POLY1305_NONCE_SIZE, CHACHA20_POLY1305_TAG_SIZE,
+            ChaCha20Poly1305,
+        },
+    },
+};
+
+/// An authenticated encryption with associated data (AEAD) algorithm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AeadAlg {
+    GcmAes,
+    /// ChaCha20-Poly1305 as specified by RFC 7539 (and RFC 8439).
+    Rfc7539ChaCha20Poly1305,
+}
+
+impl AeadAlg {
+    /// Looks up the algorithm by its name.
+    pub fn from_name(name: &str) -> Result<Self> {
+        let alg = match name {
+            "gcm(aes)" => Self::GcmAes,
+            "rfc7539(chacha20,poly1305)" => Self::Rfc7539ChaCha20Poly1305,
+            _ => return_errno_with_message!(Errno::ENOENT, "the AEAD algorithm does not exist"),
+        };
+        Ok(alg)
+    }
+
+    /// Returns the size of the IVs in bytes.
+    pub fn iv_size(self) -> usize {
+        match self {
+            Self::GcmAes => AES_GCM_NONCE_SIZE,
+            Self::Rfc7539ChaCha20Poly1305 => CHACHA20_POLY1305_NONCE_SIZE,
+        }
+    }
+
+    /// Returns the maximum (and default) size of the authentication tags in bytes.
+    pub fn max_auth_size(self) -> usize {
+        match self {
+            Self::GcmAes => AES_GCM_TAG_SIZE,
+            Self::Rfc7539ChaCha20Poly1305 => CHACHA20_POLY1305_TAG_SIZE,
+        }
+    }
+
+    /// Checks whether the authentication tags can be truncated to the size.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.18/source/crypto/gcm.c#L90>.
+    pub fn check_auth_size(self, auth_size: usize) -> Result<()> {
+        let is_valid = match self {
+            Self::GcmAes => matches!(auth_size, 4 | 8 | 12..=AES_GCM_TAG_SIZE),
+            Self::Rfc7539ChaCha20Poly1305 => auth_size == CHACHA20_POLY1305_TAG_SIZE,
+        };
+        if !is_valid {
+            return_errno_with_message!(Errno::EINVAL, "the authentication tag size is invalid");
+        }
+        Ok(())
+    }
+
+    /// Creates a cipher with the key.
+    pub fn new_cipher(self, key: &[u8]) -> Result<Box<dyn Aead>> {
+        let invalid_key = || Error::with_message(Errno::EINVAL, "the key size is invalid");
+
+        let cipher: Box<dyn Aead> = match self {
+            Self::GcmAes => Box::new(AesGcm::new(Aes::new(key).ok_or_else(invalid_key)?)),
+            Self::Rfc7539ChaCha20Poly1305 => {
+                let key: &[u8; CHACHA20_POLY1305_KEY_SIZE] =
+                    key.try_into().map_err(|_| invalid_key())?;
+                Box::new(ChaCha20Poly1305::new(key))
+            }
+        };
+        Ok(cipher)
+    }
+}
+
+/// An AEAD cipher with a key.
+pub trait Aead: Send + Sync {
+    /// Encrypts the data in place and writes the authentication tag.
+    ///
+    /// The IV must be of the size specified by [`AeadAlg::iv_size`]. The tag is truncated to
+    /// the size of the buffer, which must have been checked by [`AeadAlg::check_auth_size`].
+    fn seal(&self, iv: &[u8], aad: &[u8], data: &mut [u8], tag: &mut [u8]);
+
+    /// Verifies the authentication tag and decrypts the data in place.
+    ///
+    /// This method fails with [`Errno::EBADMSG`] and leaves the data unchanged if the tag is
+    /// invalid.
+    fn open(&self, iv: &[u8], aad: &[u8], data: &mut [u8], tag: &[u8]) -> Result<()>;
+}
+
+impl Aead for AesGcm {
+    fn seal(&self, iv: &[u8], aad: &[u8], data: &mut [u8], tag: &mut [u8]) {
+        let full_tag = AesGcm::seal(self, iv.try_into().unwrap(), aad, data);
+        tag.copy_from_slice(&full_tag[..tag.len()]);
+    }
+
+    fn open(&self, iv: &[u8], aad: &[u8], data: &mut [u8], tag: &[u8]) -> Result<()> {
+        if !AesGcm::open(self, iv.try_into().unwrap(), aad, data, tag) {
+            return_errno_with_message!(Errno::EBADMSG, "the authentication tag is invalid");
+        }
+        Ok(())
+    }
+}
+
+impl Aead for ChaCha20Poly1305 {
+    fn seal(&self, iv: &[u8], aad: &[u8], data: &mut [u8], tag: &mut [u8]) {
+        let full_tag = ChaCha20Poly1305::seal(self, iv.try_into().unwrap(), aad, data);
+        tag.copy_from_slice(&full_tag[..tag.len()]);
+    }
+
+    fn open(&self, iv: &[u8], aad: &[u8], data: &mut [u8], tag: &[u8]) -> Result<()> {
+        let is_valid = tag
+            .try_into()
+            .is_ok_and(|tag| ChaCha20Poly1305::open(self, iv.try_into().unwrap(), aad, data, tag));
+        if !is_valid {
+            return_errno_with_message!(Errno::EBADMSG, "the authentication tag is invalid");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    prelude::*,
+    util::crypto::{
+        blake2s::{BLAKE2S_DIGEST_SIZE, BLAKE2S_MAX_KEY_SIZE, Blake2s},
+        sha256::{HmacSha256, SHA256_DIGEST_SIZE, Sha256},
+        sha512::{HmacSha512, SHA512_DIGEST_SIZE, Sha512},
+    },
+};
+
+/// A hash algorithm, which may be keyed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlg {
+    Sha256,
+    Sha512,
+    /// BLAKE2s with full-length digests, which can be keyed optionally.
+    Blake2s256,
+    HmacSha256,
+    HmacSha512,
+}
+
+impl HashAlg {
+    /// Looks up the algorithm by its name.
+    pub fn from_name(name: &str) -> Result<Self> {
+        let alg = match name {
+            "sha256" => Self::Sha256,
+            "sha512" => Self::Sha512,
+            "blake2s-256" => Self::Blake2s256,
+            "hmac(sha256)" => Self::HmacSha256,
+            "hmac(sha512)" => Self::HmacSha512,
+            _ => return_errno_with_message!(Errno::ENOENT, "the hash algorithm does not exist"),
+        };
+        Ok(alg)
+    }
+
+    /// Returns the size of the digests in bytes.
+    pub fn digest_size(self) -> usize {
+        match self {
+            Self::Sha256 | Self::HmacSha256 => SHA256_DIGEST_SIZE,
+            Self::Sha512 | Self::HmacSha512 => SHA512_DIGEST_SIZE,
+            Self::Blake2s256 => BLAKE2S_DIGEST_SIZE,
+        }
+    }
+
+    /// Returns whether the algorithm cannot be used without a key.
+    pub fn requires_key(self) -> bool {
+        matches!(self, Self::HmacSha256 | Self::HmacSha512)
+    }
+
+    /// Checks whether the key can be used with the algorithm.
+    pub fn check_key(self, key: &[u8]) -> Result<()> {
+        match self {
+            Self::Sha256 | Self::Sha512 => {
+                return_errno_with_message!(Errno::ENOSYS, "the hash algorithm is not keyed")
+            }
+            Self::Blake2s256 if key.len() > BLAKE2S_MAX_KEY_SIZE => {
+                return_errno_with_message!(Errno::EINVAL, "the key is too long")
+            }
+            Self::Blake2s256 | Self::HmacSha256 | Self::HmacSha512 => Ok(()),
+        }
+    }
+
+    /// Creates a hash state, which is keyed if the key is given.
+    pub fn new_state(self, key: Option<&[u8]>) -> Result<Box<dyn HashState>> {
+        if let Some(key) = key {
+            self.check_key(key)?;
+        } else if self.requires_key() {
+            return_errno_with_message!(Errno::ENOKEY, "the hash algorithm requires a key");
+        }
+        let key = key.unwrap_or_default();
+
+        let state: Box<dyn HashState> = match self {
+            Self::Sha256 => Box::new(Sha256::new()),
+            Self::Sha512 => Box::new(Sha512::new()),
+            Self::Blake2s256 => Box::new(Blake2s::new_keyed(key, BLAKE2S_DIGEST_SIZE)),
+            Self::HmacSha256 => Box::new(HmacSha256::new(key)),
+            Self::HmacSha512 => Box::new(HmacSha512::new(key)),
+        };
+        Ok(state)
+    }
+}
+
+/// The state of a hash computation.
+pub trait HashState: Send + Sync {
+    /// Feeds the data into the state.
+    fn update(&mut self, data: &[u8]);
+
+    /// Finishes the computation and returns the digest.
+    fn finalize(self: Box<Self>) -> Vec<u8>;
+
+    /// Duplicates the state, so that the computation can be continued independently.
+    fn box_clone(&self) -> Box<dyn HashState>;
+}
+
+macro_rules! impl_hash_state {
+    ($($type:ty),*) => {
+        $(
+            impl HashState for $type {
+                fn update(&mut self, data: &[u8]) {
+                    <$type>::update(self, data);
+                }
+
+                fn finalize(self: Box<Self>) -> Vec<u8> {
+                    <$type>::finalize(*self).to_vec()
+                }
+
+                fn box_clone(&self) -> Box<dyn HashState> {
+                    Box::new(self.clone())
+                }
+            }
+        )*
+    };
+}
+
+impl_hash_state!(Sha256, Sha512, Blake2s, HmacSha256, HmacSha512);
@@ -0,0 +1,25 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The crypto API, which provides the cryptographic algorithms by their names.
+//!
+//! The algorithms are named as in Linux (e.g., `cbc(aes)` and `hmac(sha256)`), so that they
+//! can be requested by the user space via `AF_ALG` sockets and by the kernel subsystems via
+//! their configurations (e.g., the cipher specifications of dm-crypt).
+//!
+//! An algorithm is looked up by its name first, and then a transform is created from the
+//! algorithm with a key (or a seed). A transform can be shared by multiple users, except
+//! for the states of hash algorithms and random number generators.
+//!
+//! Reference: <https://docs.kernel.org/crypto/index.html>
+
+pub use self::{
+    aead::{Aead, AeadAlg},
+    hash::{HashAlg, HashState},
+    rng::{Rng, RngAlg},
+    skcipher::{Skcipher, SkcipherAlg},
+};
+
+mod aead;
+mod hash;
+mod rng;
+mod skcipher;
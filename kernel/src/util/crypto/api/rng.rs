@@ -0,0 +1,57 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use rand::{RngCore, SeedableRng, rngs::StdRng};
+
+use crate::{
+    prelude::*,
+    util::{
+        crypto::sha256::{SHA256_DIGEST_SIZE, Sha256},
+        random::getrandom,
+    },
+};
+
+/// A random number generator algorithm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RngAlg {
+    /// The default cryptographically secure generator.
+    StdRng,
+}
+
+impl RngAlg {
+    /// Looks up the algorithm by its name.
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "stdrng" => Ok(Self::StdRng),
+            _ => return_errno_with_message!(Errno::ENOENT, "the RNG algorithm does not exist"),
+        }
+    }
+
+    /// Creates a generator with the seed.
+    ///
+    /// Like the DRBGs without prediction resistance in Linux, the seed is mixed with the
+    /// randomness of the kernel, so the output is not determined by the seed alone.
+    pub fn new_rng(self, seed: &[u8]) -> Box<dyn Rng> {
+        let mut entropy = [0u8; SHA256_DIGEST_SIZE];
+        getrandom(&mut entropy);
+
+        let mut hasher = Sha256::new();
+        hasher.update(&entropy);
+        hasher.update(seed);
+
+        match self {
+            Self::StdRng => Box::new(StdRng::from_seed(hasher.finalize())),
+        }
+    }
+}
+
+/// A random number generator.
+pub trait Rng: Send + Sync {
+    /// Fills the buffer with random bytes.
+    fn generate(&mut self, buf: &mut [u8]);
+}
+
+impl Rng for StdRng {
+    fn generate(&mut self, buf: &mut [u8]) {
+        self.fill_bytes(buf);
+    }
+}
@@ -0,0 +1,174 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    prelude::*,
+    util::crypto::aes::{AES_BLOCK_SIZE, Aes, AesCbc, AesCbcCts, AesCtr, AesXts},
+};
+
+/// A symmetric key cipher algorithm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkcipherAlg {
+    EcbAes,
+    CbcAes,
+    CtrAes,
+    XtsAes,
+    CtsCbcAes,
+}
+
+impl SkcipherAlg {
+    /// Looks up the algorithm by its name.
+    pub fn from_name(name: &str) -> Result<Self> {
+        let alg = match name {
+            "ecb(aes)" => Self::EcbAes,
+            "cbc(aes)" => Self::CbcAes,
+            "ctr(aes)" => Self::CtrAes,
+            "xts(aes)" => Self::XtsAes,
+            "cts(cbc(aes))" => Self::CtsCbcAes,
+            _ => return_errno_with_message!(Errno::ENOENT, "the cipher algorithm does not exist"),
+        };
+        Ok(alg)
+    }
+
+    /// Returns the size of the blocks in bytes.
+    ///
+    /// The length of the data must be a multiple of the block size, except for the modes
+    /// with ciphertext stealing, where the data only needs to be at least one block long.
+    pub fn block_size(self) -> usize {
+        match self {
+            Self::CtrAes => 1,
+            Self::EcbAes | Self::CbcAes | Self::XtsAes | Self::CtsCbcAes => AES_BLOCK_SIZE,
+        }
+    }
+
+    /// Returns the size of the IVs in bytes.
+    pub fn iv_size(self) -> usize {
+        match self {
+            Self::EcbAes => 0,
+            Self::CbcAes | Self::CtrAes | Self::XtsAes | Self::CtsCbcAes => AES_BLOCK_SIZE,
+        }
+    }
+
+    /// Creates a cipher with the key.
+    pub fn new_cipher(self, key: &[u8]) -> Result<Box<dyn Skcipher>> {
+        let invalid_key = || Error::with_message(Errno::EINVAL, "the key size is invalid");
+        let aes = || Aes::new(key).ok_or_else(invalid_key);
+
+        let cipher: Box<dyn Skcipher> = match self {
+            Self::EcbAes => Box::new(aes()?),
+            Self::CbcAes => Box::new(AesCbc::new(aes()?)),
+            Self::CtrAes => Box::new(AesCtr::new(aes()?)),
+            Self::XtsAes => Box::new(AesXts::new(key).ok_or_else(invalid_key)?),
+            Self::CtsCbcAes => Box::new(AesCbcCts::new(aes()?)),
+        };
+        Ok(cipher)
+    }
+}
+
+/// A symmetric key cipher with a key.
+pub trait Skcipher: Send + Sync {
+    /// Encrypts the data in place with the IV.
+    ///
+    /// The IV must be of the size specified by [`SkcipherAlg::iv_size`]. For the chaining
+    /// modes (i.e., CBC and CTR), the IV is updated so that the next data can be encrypted
+    /// in a chain.
+    fn encrypt(&self, iv: &mut [u8], data: &mut [u8]) -> Result<()>;
+
+    /// Decrypts the data in place with the IV.
+    ///
+    /// The IV is treated in the same way as [`Skcipher::encrypt`].
+    fn decrypt(&self, iv: &mut [u8], data: &mut [u8]) -> Result<()>;
+}
+
+impl Skcipher for Aes {
+    fn encrypt(&self, _iv: &mut [u8], data: &mut [u8]) -> Result<()> {
+        for block in split_blocks(data)? {
+            self.encrypt_block(block);
+        }
+        Ok(())
+    }
+
+    fn decrypt(&self, _iv: &mut [u8], data: &mut [u8]) -> Result<()> {
+        for block in split_blocks(data)? {
+            self.decrypt_block(block);
+        }
+        Ok(())
+    }
+}
+
+impl Skcipher for AesCbc {
+    fn encrypt(&self, iv: &mut [u8], data: &mut [u8]) -> Result<()> {
+        check_blocks(data)?;
+        AesCbc::encrypt(self, iv.try_into().unwrap(), data);
+        Ok(())
+    }
+
+    fn decrypt(&self, iv: &mut [u8], data: &mut [u8]) -> Result<()> {
+        check_blocks(data)?;
+        AesCbc::decrypt(self, iv.try_into().unwrap(), data);
+        Ok(())
+    }
+}
+
+impl Skcipher for AesCtr {
+    fn encrypt(&self, iv: &mut [u8], data: &mut [u8]) -> Result<()> {
+        self.apply_keystream(iv.try_into().unwrap(), data);
+        Ok(())
+    }
+
+    fn decrypt(&self, iv: &mut [u8], data: &mut [u8]) -> Result<()> {
+        self.apply_keystream(iv.try_into().unwrap(), data);
+        Ok(())
+    }
+}
+
+impl Skcipher for AesXts {
+    fn encrypt(&self, iv: &mut [u8], data: &mut [u8]) -> Result<()> {
+        check_blocks(data)?;
+        AesXts::encrypt(self, (&*iv).try_into().unwrap(), data);
+        Ok(())
+    }
+
+    fn decrypt(&self, iv: &mut [u8], data: &mut [u8]) -> Result<()> {
+        check_blocks(data)?;
+        AesXts::decrypt(self, (&*iv).try_into().unwrap(), data);
+        Ok(())
+    }
+}
+
+impl Skcipher for AesCbcCts {
+    fn encrypt(&self, iv: &mut [u8], data: &mut [u8]) -> Result<()> {
+        check_at_least_one_block(data)?;
+        AesCbcCts::encrypt(self, (&*iv).try_into().unwrap(), data);
+        Ok(())
+    }
+
+    fn decrypt(&self, iv: &mut [u8], data: &mut [u8]) -> Result<()> {
+        check_at_least_one_block(data)?;
+        AesCbcCts::decrypt(self, (&*iv).try_into().unwrap(), data);
+        Ok(())
+    }
+}
+
+fn check_blocks(data: &[u8]) -> Result<()> {
+    if data.len() % AES_BLOCK_SIZE != 0 {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "the data length is not a multiple of the block size"
+        );
+    }
+    Ok(())
+}
+
+fn check_at_least_one_block(data: &[u8]) -> Result<()> {
+    if data.len() < AES_BLOCK_SIZE {
+        return_errno_with_message!(Errno::EINVAL, "the data is shorter than a block");
+    }
+    Ok(())
+}
+
+fn split_blocks(data: &mut [u8]) -> Result<impl Iterator<Item = &mut [u8; AES_BLOCK_SIZE]>> {
+    check_blocks(data)?;
+    Ok(data
+        .chunks_exact_mut(AES_BLOCK_SIZE)
+        .map(|chunk| chunk.try_into().unwrap()))
+}
@@ -0,0 +1,269 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The BLAKE2s hash function and the HMAC construction on top of it.
+//!
+//! References:
+//! - <https://datatracker.ietf.org/doc/html/rfc7693>
+//! - <https://datatracker.ietf.org/doc/html/rfc2104>
+
+/// The size of a full-length BLAKE2s digest in bytes.
+pub const BLAKE2S_DIGEST_SIZE: usize = 32;
+
+/// The maximum size of a BLAKE2s key in bytes.
+pub const BLAKE2S_MAX_KEY_SIZE: usize = 32;
+
+const BLOCK_SIZE: usize = 64;
+
+/// The BLAKE2s hash function, optionally keyed.
+#[derive(Clone)]
+pub struct Blake2s {
+    state: [u32; 8],
+    buf: [u8; BLOCK_SIZE],
+    buf_len: usize,
+    total_len: u64,
+    digest_len: usize,
+}
+
+impl Blake2s {
+    /// Creates an unkeyed hasher that produces digests of [`BLAKE2S_DIGEST_SIZE`] bytes.
+    pub fn new() -> Self {
+        Self::new_keyed(&[], BLAKE2S_DIGEST_SIZE)
+    }
+
+    /// Creates a keyed hasher that produces digests of `digest_len` bytes.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the key is longer than [`BLAKE2S_MAX_KEY_SIZE`] bytes, or if the
+    /// digest length is zero or longer than [`BLAKE2S_DIGEST_SIZE`] bytes.
+    pub fn new_keyed(key: &[u8], digest_len: usize) -> Self {
+        assert!(key.len() <= BLAKE2S_MAX_KEY_SIZE);
+        assert!((1..=BLAKE2S_DIGEST_SIZE).contains(&digest_len));
+
+        let mut state = INITIAL_STATE;
+        state[0] ^= 0x0101_0000 ^ ((key.len() as u32) << 8) ^ digest_len as u32;
+
+        let mut hasher = Self {
+            state,
+            buf: [0; BLOCK_SIZE],
+            buf_len: 0,
+            total_len: 0,
+            digest_len,
+        };
+        if !key.is_empty() {
+            hasher.buf[..key.len()].copy_from_slice(key);
+            hasher.buf_len = BLOCK_SIZE;
+        }
+        hasher
+    }
+
+    /// Feeds the data into the hasher.
+    pub fn update(&mut self, mut data: &[u8]) {
+        // The last block must be compressed with the finalization flag, so a full buffer is only
+        // compressed when more data arrives.
+        while !data.is_empty() {
+            if self.buf_len == BLOCK_SIZE {
+                self.total_len += BLOCK_SIZE as u64;
+                let block = self.buf;
+                self.compress(&block, false);
+                self.buf_len = 0;
+            }
+
+            let len = data.len().min(BLOCK_SIZE - self.buf_len);
+            self.buf[self.buf_len..self.buf_len + len].copy_from_slice(&data[..len]);
+            self.buf_len += len;
+            data = &data[len..];
+        }
+    }
+
+    /// Finishes the hashing and writes the digest to `out`.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the length of `out` differs from the digest length.
+    pub fn finalize_into(mut self, out: &mut [u8]) {
+        assert_eq!(out.len(), self.digest_len);
+
+        self.total_len += self.buf_len as u64;
+        self.buf[self.buf_len..].fill(0);
+        let block = self.buf;
+        self.compress(&block, true);
+
+        let mut digest = [0u8; BLAKE2S_DIGEST_SIZE];
+        for (chunk, word) in digest.chunks_exact_mut(4).zip(self.state) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        out.copy_from_slice(&digest[..self.digest_len]);
+    }
+
+    /// Finishes the hashing and returns the full-length digest.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the hasher does not produce full-length digests.
+    pub fn finalize(self) -> [u8; BLAKE2S_DIGEST_SIZE] {
+        let mut digest = [0u8; BLAKE2S_DIGEST_SIZE];
+        self.finalize_into(&mut digest);
+        digest
+    }
+
+    fn compress(&mut self, block: &[u8; BLOCK_SIZE], is_last: bool) {
+        let mut m = [0u32; 16];
+        for (word, chunk) in m.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_le_bytes(chunk.try_into().unwrap());
+        }
+
+        let mut v = [0u32; 16];
+        v[..8].copy_from_slice(&self.state);
+        v[8..].copy_from_slice(&INITIAL_STATE);
+        v[12] ^= self.total_len as u32;
+        v[13] ^= (self.total_len >> 32) as u32;
+        if is_last {
+            v[14] = !v[14];
+        }
+
+        for s in SIGMA.iter() {
+            mix(&mut v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+            mix(&mut v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+            mix(&mut v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+            mix(&mut v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+            mix(&mut v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+            mix(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+            mix(&mut v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+            mix(&mut v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+        }
+
+        for (i, state) in self.state.iter_mut().enumerate() {
+            *state ^= v[i] ^ v[i + 8];
+        }
+    }
+}
+
+impl Default for Blake2s {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn mix(v: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize, x: u32, y: u32) {
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(12);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+    v[d] = (v[d] ^ v[a]).rotate_right(8);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(7);
+}
+
+/// Computes the full-length BLAKE2s digest of the concatenated data.
+pub fn blake2s(data: &[&[u8]]) -> [u8; BLAKE2S_DIGEST_SIZE] {
+    let mut hasher = Blake2s::new();
+    for part in data {
+        hasher.update(part);
+    }
+    hasher.finalize()
+}
+
+/// The HMAC-BLAKE2s message authentication code.
+#[derive(Clone)]
+pub struct HmacBlake2s {
+    inner: Blake2s,
+    outer: Blake2s,
+}
+
+impl HmacBlake2s {
+    /// Creates an authenticator with the key.
+    pub fn new(key: &[u8]) -> Self {
+        let mut block_key = [0u8; BLOCK_SIZE];
+        if key.len() > BLOCK_SIZE {
+            block_key[..BLAKE2S_DIGEST_SIZE].copy_from_slice(&blake2s(&[key]));
+        } else {
+            block_key[..key.len()].copy_from_slice(key);
+        }
+
+        let mut inner = Blake2s::new();
+        inner.update(&block_key.map(|byte| byte ^ 0x36));
+        let mut outer = Blake2s::new();
+        outer.update(&block_key.map(|byte| byte ^ 0x5c));
+        Self { inner, outer }
+    }
+
+    /// Feeds the message into the authenticator.
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    /// Finishes the authentication and returns the code.
+    pub fn finalize(self) -> [u8; BLAKE2S_DIGEST_SIZE] {
+        let mut outer = self.outer;
+        outer.update(&self.inner.finalize());
+        outer.finalize()
+    }
+}
+
+/// Computes the HMAC-BLAKE2s of the concatenated message with the key.
+pub fn hmac_blake2s(key: &[u8], message: &[&[u8]]) -> [u8; BLAKE2S_DIGEST_SIZE] {
+    let mut hmac = HmacBlake2s::new(key);
+    for part in message {
+        hmac.update(part);
+    }
+    hmac.finalize()
+}
+
+const INITIAL_STATE: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    fn to_hex(bytes: &[u8]) -> alloc::string::String {
+        use core::fmt::Write;
+
+        let mut hex = alloc::string::String::new();
+        for byte in bytes {
+            write!(hex, "{:02x}", byte).unwrap();
+        }
+        hex
+    }
+
+    #[ktest]
+    fn blake2s_empty_and_abc() {
+        assert_eq!(
+            to_hex(&blake2s(&[])),
+            "69217a3079908094e11121d042354a7c1f55b6482ca1a51e1b250dfd1ed0eef9"
+        );
+        assert_eq!(
+            to_hex(&blake2s(&[b"a", b"bc"])),
+            "508c5e8c327c14e2e1a72ba34eeb452f37458b209ed63a294d999b4c86675982"
+        );
+    }
+
+    #[ktest]
+    fn blake2s_multiple_blocks() {
+        let data = [0x61u8; 200];
+
+        let mut hasher = Blake2s::new();
+        for chunk in data.chunks(7) {
+            hasher.update(chunk);
+        }
+        assert_eq!(hasher.finalize(), blake2s(&[&data]));
+    }
+}
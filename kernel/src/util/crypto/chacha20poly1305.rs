@@ -0,0 +1,318 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The ChaCha20-Poly1305 AEAD construction.
+//!
+//! Reference: <https://datatracker.ietf.org/doc/html/rfc8439>
+
+/// The size of a ChaCha20-Poly1305 key in bytes.
+pub const CHACHA20_POLY1305_KEY_SIZE: usize = 32;
+
+/// The size of a ChaCha20-Poly1305 nonce in bytes.
+pub const CHACHA20_POLY1305_NONCE_SIZE: usize = 12;
+
+/// The size of a ChaCha20-Poly1305 authentication tag in bytes.
+pub const CHACHA20_POLY1305_TAG_SIZE: usize = 16;
+
+/// The ChaCha20-Poly1305 AEAD cipher with a 256-bit key.
+pub struct ChaCha20Poly1305 {
+    key: [u32; 8],
+}
+
+impl ChaCha20Poly1305 {
+    /// Creates a cipher with the key.
+    pub fn new(key: &[u8; CHACHA20_POLY1305_KEY_SIZE]) -> Self {
+        let mut words = [0u32; 8];
+        for (word, chunk) in words.iter_mut().zip(key.chunks_exact(4)) {
+            *word = u32::from_le_bytes(chunk.try_into().unwrap());
+        }
+        Self { key: words }
+    }
+
+    /// Encrypts the data in place and returns the authentication tag.
+    ///
+    /// The tag also authenticates the additional data, which is not encrypted.
+    pub fn seal(
+        &self,
+        nonce: &[u8; CHACHA20_POLY1305_NONCE_SIZE],
+        aad: &[u8],
+        data: &mut [u8],
+    ) -> [u8; CHACHA20_POLY1305_TAG_SIZE] {
+        self.apply_keystream(nonce, 1, data);
+        self.compute_tag(nonce, aad, data)
+    }
+
+    /// Verifies the authentication tag and decrypts the data in place.
+    ///
+    /// This method returns `false` and leaves the data unchanged if the tag is invalid.
+    #[must_use]
+    pub fn open(
+        &self,
+        nonce: &[u8; CHACHA20_POLY1305_NONCE_SIZE],
+        aad: &[u8],
+        data: &mut [u8],
+        tag: &[u8; CHACHA20_POLY1305_TAG_SIZE],
+    ) -> bool {
+        let expected_tag = self.compute_tag(nonce, aad, data);
+
+        // Compare the tags in constant time.
+        let diff = expected_tag
+            .iter()
+            .zip(tag)
+            .fold(0u8, |diff, (lhs, rhs)| diff | (lhs ^ rhs));
+        if diff != 0 {
+            return false;
+        }
+
+        self.apply_keystream(nonce, 1, data);
+        true
+    }
+
+    fn compute_tag(
+        &self,
+        nonce: &[u8; CHACHA20_POLY1305_NONCE_SIZE],
+        aad: &[u8],
+        ciphertext: &[u8],
+    ) -> [u8; CHACHA20_POLY1305_TAG_SIZE] {
+        let mut poly_key = [0u8; 32];
+        self.apply_keystream(nonce, 0, &mut poly_key);
+
+        let mut poly = Poly1305::new(&poly_key);
+        poly.update_padded(aad);
+        poly.update_padded(ciphertext);
+
+        let mut lens = [0u8; 16];
+        lens[..8].copy_from_slice(&(aad.len() as u64).to_le_bytes());
+        lens[8..].copy_from_slice(&(ciphertext.len() as u64).to_le_bytes());
+        poly.update_padded(&lens);
+
+        poly.finalize()
+    }
+
+    /// XORs the data with the ChaCha20 keystream starting at the block counter.
+    fn apply_keystream(
+        &self,
+        nonce: &[u8; CHACHA20_POLY1305_NONCE_SIZE],
+        mut counter: u32,
+        data: &mut [u8],
+    ) {
+        for chunk in data.chunks_mut(64) {
+            let block = self.block(nonce, counter);
+            for (byte, key_byte) in chunk.iter_mut().zip(block) {
+                *byte ^= key_byte;
+            }
+            counter = counter.wrapping_add(1);
+        }
+    }
+
+    /// Computes a ChaCha20 block.
+    fn block(&self, nonce: &[u8; CHACHA20_POLY1305_NONCE_SIZE], counter: u32) -> [u8; 64] {
+        let mut state = [0u32; 16];
+        state[..4].copy_from_slice(&SIGMA);
+        state[4..12].copy_from_slice(&self.key);
+        state[12] = counter;
+        for (word, chunk) in state[13..].iter_mut().zip(nonce.chunks_exact(4)) {
+            *word = u32::from_le_bytes(chunk.try_into().unwrap());
+        }
+
+        let mut x = state;
+        for _ in 0..10 {
+            quarter_round(&mut x, 0, 4, 8, 12);
+            quarter_round(&mut x, 1, 5, 9, 13);
+            quarter_round(&mut x, 2, 6, 10, 14);
+            quarter_round(&mut x, 3, 7, 11, 15);
+            quarter_round(&mut x, 0, 5, 10, 15);
+            quarter_round(&mut x, 1, 6, 11, 12);
+            quarter_round(&mut x, 2, 7, 8, 13);
+            quarter_round(&mut x, 3, 4, 9, 14);
+        }
+
+        let mut block = [0u8; 64];
+        for ((chunk, word), init) in block.chunks_exact_mut(4).zip(x).zip(state) {
+            chunk.copy_from_slice(&word.wrapping_add(init).to_le_bytes());
+        }
+        block
+    }
+}
+
+fn quarter_round(x: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    x[a] = x[a].wrapping_add(x[b]);
+    x[d] = (x[d] ^ x[a]).rotate_left(16);
+    x[c] = x[c].wrapping_add(x[d]);
+    x[b] = (x[b] ^ x[c]).rotate_left(12);
+    x[a] = x[a].wrapping_add(x[b]);
+    x[d] = (x[d] ^ x[a]).rotate_left(8);
+    x[c] = x[c].wrapping_add(x[d]);
+    x[b] = (x[b] ^ x[c]).rotate_left(7);
+}
+
+/// The constants of ChaCha20, which is "expand 32-byte k" in little endian.
+const SIGMA: [u32; 4] = [0x61707865, 0x3320646e, 0x79622d32, 0x6b206574];
+
+/// The Poly1305 one-time authenticator.
+///
+/// The accumulator and the key are represented in 26-bit limbs, so the products fit in `u64`.
+struct Poly1305 {
+    r: [u32; 5],
+    h: [u32; 5],
+    pad: [u32; 4],
+}
+
+impl Poly1305 {
+    fn new(key: &[u8; 32]) -> Self {
+        let r = [
+            le32(&key[0..]) & 0x3ff_ffff,
+            (le32(&key[3..]) >> 2) & 0x3ff_ff03,
+            (le32(&key[6..]) >> 4) & 0x3ff_c0ff,
+            (le32(&key[9..]) >> 6) & 0x3f0_3fff,
+            (le32(&key[12..]) >> 8) & 0x00f_ffff,
+        ];
+        let pad = [
+            le32(&key[16..]),
+            le32(&key[20..]),
+            le32(&key[24..]),
+            le32(&key[28..]),
+        ];
+        Self { r, h: [0; 5], pad }
+    }
+
+    /// Feeds the data into the authenticator, padding it with zeros to a multiple of 16 bytes.
+    fn update_padded(&mut self, data: &[u8]) {
+        for chunk in data.chunks(16) {
+            let mut block = [0u8; 16];
+            block[..chunk.len()].copy_from_slice(chunk);
+            self.process_block(&block);
+        }
+    }
+
+    fn process_block(&mut self, block: &[u8; 16]) {
+        const MASK: u32 = 0x3ff_ffff;
+
+        let [r0, r1, r2, r3, r4] = self.r.map(u64::from);
+        let [s1, s2, s3, s4] = [r1 * 5, r2 * 5, r3 * 5, r4 * 5];
+
+        let h0 = (self.h[0] + (le32(&block[0..]) & MASK)) as u64;
+        let h1 = (self.h[1] + ((le32(&block[3..]) >> 2) & MASK)) as u64;
+        let h2 = (self.h[2] + ((le32(&block[6..]) >> 4) & MASK)) as u64;
+        let h3 = (self.h[3] + ((le32(&block[9..]) >> 6) & MASK)) as u64;
+        // All blocks are full blocks (possibly padded with zeros), so the 129th bit is always set.
+        let h4 = (self.h[4] + ((le32(&block[12..]) >> 8) | (1 << 24))) as u64;
+
+        let d0 = h0 * r0 + h1 * s4 + h2 * s3 + h3 * s2 + h4 * s1;
+        let mut d1 = h0 * r1 + h1 * r0 + h2 * s4 + h3 * s3 + h4 * s2;
+        let mut d2 = h0 * r2 + h1 * r1 + h2 * r0 + h3 * s4 + h4 * s3;
+        let mut d3 = h0 * r3 + h1 * r2 + h2 * r1 + h3 * r0 + h4 * s4;
+        let mut d4 = h0 * r4 + h1 * r3 + h2 * r2 + h3 * r1 + h4 * r0;
+
+        d1 += d0 >> 26;
+        d2 += d1 >> 26;
+        d3 += d2 >> 26;
+        d4 += d3 >> 26;
+        let h0 = (d0 & MASK as u64) + (d4 >> 26) * 5;
+        let h1 = (d1 & MASK as u64) + (h0 >> 26);
+
+        self.h = [
+            h0 as u32 & MASK,
+            h1 as u32,
+            d2 as u32 & MASK,
+            d3 as u32 & MASK,
+            d4 as u32 & MASK,
+        ];
+    }
+
+    fn finalize(self) -> [u8; 16] {
+        const MASK: u32 = 0x3ff_ffff;
+
+        // Fully carry the accumulator.
+        let [mut h0, mut h1, mut h2, mut h3, mut h4] = self.h;
+        h2 += h1 >> 26;
+        h1 &= MASK;
+        h3 += h2 >> 26;
+        h2 &= MASK;
+        h4 += h3 >> 26;
+        h3 &= MASK;
+        h0 += (h4 >> 26) * 5;
+        h4 &= MASK;
+        h1 += h0 >> 26;
+        h0 &= MASK;
+
+        // Compute `h - p` and select it if it is not negative.
+        let mut g0 = h0.wrapping_add(5);
+        let mut g1 = h1.wrapping_add(g0 >> 26);
+        g0 &= MASK;
+        let mut g2 = h2.wrapping_add(g1 >> 26);
+        g1 &= MASK;
+        let mut g3 = h3.wrapping_add(g2 >> 26);
+        g2 &= MASK;
+        let g4 = h4.wrapping_add(g3 >> 26).wrapping_sub(1 << 26);
+        g3 &= MASK;
+
+        let select_g = (g4 >> 31).wrapping_sub(1);
+        h0 = (h0 & !select_g) | (g0 & select_g);
+        h1 = (h1 & !select_g) | (g1 & select_g);
+        h2 = (h2 & !select_g) | (g2 & select_g);
+        h3 = (h3 & !select_g) | (g3 & select_g);
+        h4 = (h4 & !select_g) | (g4 & select_g);
+
+        // Compute `(h + pad) % 2^128`.
+        let words = [
+            h0 | (h1 << 26),
+            (h1 >> 6) | (h2 << 20),
+            (h2 >> 12) | (h3 << 14),
+            (h3 >> 18) | (h4 << 8),
+        ];
+        let mut tag = [0u8; 16];
+        let mut carry = 0u64;
+        for ((chunk, word), pad) in tag.chunks_exact_mut(4).zip(words).zip(self.pad) {
+            let sum = word as u64 + pad as u64 + carry;
+            chunk.copy_from_slice(&(sum as u32).to_le_bytes());
+            carry = sum >> 32;
+        }
+        tag
+    }
+}
+
+fn le32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes[..4].try_into().unwrap())
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    fn to_hex(bytes: &[u8]) -> alloc::string::String {
+        use core::fmt::Write;
+
+        let mut hex = alloc::string::String::new();
+        for byte in bytes {
+            write!(hex, "{:02x}", byte).unwrap();
+        }
+        hex
+    }
+
+    #[ktest]
+    fn chacha20_poly1305_rfc8439() {
+        let key = core::array::from_fn(|i| 0x80 + i as u8);
+        let nonce = [
+            0x07, 0x00, 0x00, 0x00, 0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47,
+        ];
+        let aad = [
+            0x50, 0x51, 0x52, 0x53, 0xc0, 0xc1, 0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7,
+        ];
+        let plaintext = b"Ladies and Gentlemen of the class of '99: If I could offer you only \
+                          one tip for the future, sunscreen would be it.";
+
+        let cipher = ChaCha20Poly1305::new(&key);
+        let mut data = *plaintext;
+        let tag = cipher.seal(&nonce, &aad, &mut data);
+        assert_eq!(to_hex(&data[..16]), "d31a8d34648e60db7b86afbc53ef7ec2");
+        assert_eq!(to_hex(&tag), "1ae10b594f09e26a7e902ecbd0600691");
+
+        let mut bad_tag = tag;
+        bad_tag[0] ^= 1;
+        assert!(!cipher.open(&nonce, &aad, &mut data, &bad_tag));
+        assert!(cipher.open(&nonce, &aad, &mut data, &tag));
+        assert_eq!(&data, plaintext);
+    }
+}
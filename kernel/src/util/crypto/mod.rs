@@ -0,0 +1,11 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Cryptographic primitives implemented in software, and the crypto API on top of them.
+
+pub mod aes;
+pub mod api;
+pub mod blake2s;
+pub mod chacha20poly1305;
+pub mod sha256;
+pub mod sha512;
+pub mod x25519;
@@ -0,0 +1,341 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The SHA-512 hash function and the HMAC and HKDF constructions on top of it.
+//!
+//! References:
+//! - <https://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.180-4.pdf>
+//! - <https://datatracker.ietf.org/doc/html/rfc2104>
+//! - <https://datatracker.ietf.org/doc/html/rfc5869>
+
+/// The size of a SHA-512 digest in bytes.
+pub const SHA512_DIGEST_SIZE: usize = 64;
+
+const BLOCK_SIZE: usize = 128;
+
+/// The SHA-512 hash function.
+#[derive(Clone)]
+pub struct Sha512 {
+    state: [u64; 8],
+    buf: [u8; BLOCK_SIZE],
+    buf_len: usize,
+    total_len: u128,
+}
+
+impl Sha512 {
+    /// Creates a hasher with the initial state.
+    pub fn new() -> Self {
+        Self {
+            state: INITIAL_STATE,
+            buf: [0; BLOCK_SIZE],
+            buf_len: 0,
+            total_len: 0,
+        }
+    }
+
+    /// Feeds the data into the hasher.
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.total_len += data.len() as u128;
+
+        if self.buf_len > 0 {
+            let len = data.len().min(BLOCK_SIZE - self.buf_len);
+            self.buf[self.buf_len..self.buf_len + len].copy_from_slice(&data[..len]);
+            self.buf_len += len;
+            data = &data[len..];
+            if self.buf_len < BLOCK_SIZE {
+                return;
+            }
+            let block = self.buf;
+            self.compress(&block);
+            self.buf_len = 0;
+        }
+
+        let mut chunks = data.chunks_exact(BLOCK_SIZE);
+        for block in &mut chunks {
+            self.compress(block.try_into().unwrap());
+        }
+        let remainder = chunks.remainder();
+        self.buf[..remainder.len()].copy_from_slice(remainder);
+        self.buf_len = remainder.len();
+    }
+
+    /// Finishes the hashing and returns the digest.
+    pub fn finalize(mut self) -> [u8; SHA512_DIGEST_SIZE] {
+        let bit_len = self.total_len.wrapping_mul(8);
+
+        let mut padding = [0u8; BLOCK_SIZE * 2];
+        padding[0] = 0x80;
+        let pad_len = if self.buf_len < BLOCK_SIZE - 16 {
+            BLOCK_SIZE - self.buf_len
+        } else {
+            BLOCK_SIZE * 2 - self.buf_len
+        };
+        padding[pad_len - 16..pad_len].copy_from_slice(&bit_len.to_be_bytes());
+        self.update(&padding[..pad_len]);
+        debug_assert_eq!(self.buf_len, 0);
+
+        let mut digest = [0u8; SHA512_DIGEST_SIZE];
+        for (chunk, word) in digest.chunks_exact_mut(8).zip(self.state) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        digest
+    }
+
+    fn compress(&mut self, block: &[u8; BLOCK_SIZE]) {
+        let mut w = [0u64; 80];
+        for (word, chunk) in w.iter_mut().zip(block.chunks_exact(8)) {
+            *word = u64::from_be_bytes(chunk.try_into().unwrap());
+        }
+        for i in 16..80 {
+            let s0 = w[i - 15].rotate_right(1) ^ w[i - 15].rotate_right(8) ^ (w[i - 15] >> 7);
+            let s1 = w[i - 2].rotate_right(19) ^ w[i - 2].rotate_right(61) ^ (w[i - 2] >> 6);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+        for (k, w) in ROUND_CONSTANTS.iter().zip(w) {
+            let s1 = e.rotate_right(14) ^ e.rotate_right(18) ^ e.rotate_right(41);
+            let ch = (e & f) ^ (!e & g);
+            let temp1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(*k)
+                .wrapping_add(w);
+            let s0 = a.rotate_right(28) ^ a.rotate_right(34) ^ a.rotate_right(39);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let temp2 = s0.wrapping_add(maj);
+
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(temp1);
+            d = c;
+            c = b;
+            b = a;
+            a = temp1.wrapping_add(temp2);
+        }
+
+        for (state, value) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+}
+
+impl Default for Sha512 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Computes the SHA-512 digest of the data.
+pub fn sha512(data: &[u8]) -> [u8; SHA512_DIGEST_SIZE] {
+    let mut hasher = Sha512::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// The HMAC-SHA512 message authentication code.
+#[derive(Clone)]
+pub struct HmacSha512 {
+    inner: Sha512,
+    outer: Sha512,
+}
+
+impl HmacSha512 {
+    /// Creates an authenticator with the key.
+    pub fn new(key: &[u8]) -> Self {
+        let mut block_key = [0u8; BLOCK_SIZE];
+        if key.len() > BLOCK_SIZE {
+            block_key[..SHA512_DIGEST_SIZE].copy_from_slice(&sha512(key));
+        } else {
+            block_key[..key.len()].copy_from_slice(key);
+        }
+
+        let mut inner = Sha512::new();
+        inner.update(&block_key.map(|byte| byte ^ 0x36));
+        let mut outer = Sha512::new();
+        outer.update(&block_key.map(|byte| byte ^ 0x5c));
+        Self { inner, outer }
+    }
+
+    /// Feeds the message into the authenticator.
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    /// Finishes the authentication and returns the code.
+    pub fn finalize(self) -> [u8; SHA512_DIGEST_SIZE] {
+        let mut outer = self.outer;
+        outer.update(&self.inner.finalize());
+        outer.finalize()
+    }
+}
+
+/// Computes the HMAC-SHA512 of the message with the key.
+pub fn hmac_sha512(key: &[u8], message: &[u8]) -> [u8; SHA512_DIGEST_SIZE] {
+    let mut hmac = HmacSha512::new(key);
+    hmac.update(message);
+    hmac.finalize()
+}
+
+/// Extracts a pseudorandom key from the input keying material with HKDF-SHA512.
+pub fn hkdf_sha512_extract(salt: &[u8], ikm: &[u8]) -> [u8; SHA512_DIGEST_SIZE] {
+    hmac_sha512(salt, ikm)
+}
+
+/// Expands the pseudorandom key into the output keying material with HKDF-SHA512.
+///
+/// The info is given in parts, which are concatenated.
+///
+/// # Panics
+///
+/// This function panics if the output is longer than 255 digests.
+pub fn hkdf_sha512_expand(prk: &[u8; SHA512_DIGEST_SIZE], info: &[&[u8]], okm: &mut [u8]) {
+    assert!(okm.len() <= 255 * SHA512_DIGEST_SIZE);
+
+    let base = HmacSha512::new(prk);
+    let mut prev = [0u8; SHA512_DIGEST_SIZE];
+    for (i, chunk) in okm.chunks_mut(SHA512_DIGEST_SIZE).enumerate() {
+        let mut hmac = base.clone();
+        if i > 0 {
+            hmac.update(&prev);
+        }
+        for part in info {
+            hmac.update(part);
+        }
+        hmac.update(&[i as u8 + 1]);
+        prev = hmac.finalize();
+        chunk.copy_from_slice(&prev[..chunk.len()]);
+    }
+}
+
+const INITIAL_STATE: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+const ROUND_CONSTANTS: [u64; 80] = [
+    0x428a2f98d728ae22,
+    0x7137449123ef65cd,
+    0xb5c0fbcfec4d3b2f,
+    0xe9b5dba58189dbbc,
+    0x3956c25bf348b538,
+    0x59f111f1b605d019,
+    0x923f82a4af194f9b,
+    0xab1c5ed5da6d8118,
+    0xd807aa98a3030242,
+    0x12835b0145706fbe,
+    0x243185be4ee4b28c,
+    0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f,
+    0x80deb1fe3b1696b1,
+    0x9bdc06a725c71235,
+    0xc19bf174cf692694,
+    0xe49b69c19ef14ad2,
+    0xefbe4786384f25e3,
+    0x0fc19dc68b8cd5b5,
+    0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275,
+    0x4a7484aa6ea6e483,
+    0x5cb0a9dcbd41fbd4,
+    0x76f988da831153b5,
+    0x983e5152ee66dfab,
+    0xa831c66d2db43210,
+    0xb00327c898fb213f,
+    0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2,
+    0xd5a79147930aa725,
+    0x06ca6351e003826f,
+    0x142929670a0e6e70,
+    0x27b70a8546d22ffc,
+    0x2e1b21385c26c926,
+    0x4d2c6dfc5ac42aed,
+    0x53380d139d95b3df,
+    0x650a73548baf63de,
+    0x766a0abb3c77b2a8,
+    0x81c2c92e47edaee6,
+    0x92722c851482353b,
+    0xa2bfe8a14cf10364,
+    0xa81a664bbc423001,
+    0xc24b8b70d0f89791,
+    0xc76c51a30654be30,
+    0xd192e819d6ef5218,
+    0xd69906245565a910,
+    0xf40e35855771202a,
+    0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8,
+    0x1e376c085141ab53,
+    0x2748774cdf8eeb99,
+    0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63,
+    0x4ed8aa4ae3418acb,
+    0x5b9cca4f7763e373,
+    0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc,
+    0x78a5636f43172f60,
+    0x84c87814a1f0ab72,
+    0x8cc702081a6439ec,
+    0x90befffa23631e28,
+    0xa4506cebde82bde9,
+    0xbef9a3f7b2c67915,
+    0xc67178f2e372532b,
+    0xca273eceea26619c,
+    0xd186b8c721c0c207,
+    0xeada7dd6cde0eb1e,
+    0xf57d4f7fee6ed178,
+    0x06f067aa72176fba,
+    0x0a637dc5a2c898a6,
+    0x113f9804bef90dae,
+    0x1b710b35131c471b,
+    0x28db77f523047d84,
+    0x32caab7b40c72493,
+    0x3c9ebe0a15c9bebc,
+    0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6,
+    0x597f299cfc657e2a,
+    0x5fcb6fab3ad6faec,
+    0x6c44198c4a475817,
+];
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    fn to_hex(bytes: &[u8]) -> alloc::string::String {
+        use core::fmt::Write;
+
+        let mut hex = alloc::string::String::new();
+        for byte in bytes {
+            write!(hex, "{:02x}", byte).unwrap();
+        }
+        hex
+    }
+
+    #[ktest]
+    fn sha512_abc() {
+        assert_eq!(
+            to_hex(&sha512(b"abc")),
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+             2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+        );
+    }
+
+    #[ktest]
+    fn hmac_sha512_rfc4231() {
+        assert_eq!(
+            to_hex(&hmac_sha512(&[0x0b; 20], b"Hi There")),
+            "87aa7cdea5ef619d4ff0b4241a1d6cb02379f4e2ce4ec2787ad0b30545e17cde\
+             daa833b7d6b8a702038b274eaea3f4e4be9d914eeb61f1702e696c203a126854"
+        );
+    }
+}
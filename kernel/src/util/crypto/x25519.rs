@@ -0,0 +1,246 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The X25519 Diffie-Hellman function.
+//!
+//! Reference: <https://datatracker.ietf.org/doc/html/rfc7748>
+
+/// The size of X25519 keys and shared secrets in bytes.
+pub const X25519_KEY_SIZE: usize = 32;
+
+/// Computes the X25519 function of the scalar and the u-coordinate.
+///
+/// The scalar is clamped as required by X25519 before it is used.
+pub fn x25519(scalar: &[u8; X25519_KEY_SIZE], u: &[u8; X25519_KEY_SIZE]) -> [u8; X25519_KEY_SIZE] {
+    let mut k = *scalar;
+    k[0] &= 248;
+    k[31] &= 127;
+    k[31] |= 64;
+
+    let x1 = FieldElement::from_bytes(u);
+    let mut x2 = FieldElement::ONE;
+    let mut z2 = FieldElement::ZERO;
+    let mut x3 = x1;
+    let mut z3 = FieldElement::ONE;
+
+    // The Montgomery ladder, which runs in constant time.
+    let mut swap = 0;
+    for t in (0..255).rev() {
+        let k_t = ((k[t / 8] >> (t % 8)) & 1) as u64;
+        swap ^= k_t;
+        FieldElement::cswap(swap, &mut x2, &mut x3);
+        FieldElement::cswap(swap, &mut z2, &mut z3);
+        swap = k_t;
+
+        let a = x2.add(&z2);
+        let aa = a.square();
+        let b = x2.sub(&z2);
+        let bb = b.square();
+        let e = aa.sub(&bb);
+        let c = x3.add(&z3);
+        let d = x3.sub(&z3);
+        let da = d.mul(&a);
+        let cb = c.mul(&b);
+        x3 = da.add(&cb).square();
+        z3 = x1.mul(&da.sub(&cb).square());
+        x2 = aa.mul(&bb);
+        z2 = e.mul(&aa.add(&e.mul_small(A24)));
+    }
+    FieldElement::cswap(swap, &mut x2, &mut x3);
+    FieldElement::cswap(swap, &mut z2, &mut z3);
+
+    x2.mul(&z2.invert()).to_bytes()
+}
+
+/// Computes the public key of the private key.
+pub fn x25519_public_key(private_key: &[u8; X25519_KEY_SIZE]) -> [u8; X25519_KEY_SIZE] {
+    const BASE_POINT: [u8; X25519_KEY_SIZE] = {
+        let mut point = [0; X25519_KEY_SIZE];
+        point[0] = 9;
+        point
+    };
+
+    x25519(private_key, &BASE_POINT)
+}
+
+/// The constant `(486662 - 2) / 4` used in the Montgomery ladder.
+const A24: u64 = 121665;
+
+/// An element of the field `GF(2^255 - 19)`.
+///
+/// The element is represented in five 51-bit limbs, so the products fit in `u128`.
+#[derive(Clone, Copy)]
+struct FieldElement([u64; 5]);
+
+const LOW_51_BITS: u64 = (1 << 51) - 1;
+
+impl FieldElement {
+    const ZERO: Self = Self([0; 5]);
+    const ONE: Self = Self([1, 0, 0, 0, 0]);
+
+    fn from_bytes(bytes: &[u8; X25519_KEY_SIZE]) -> Self {
+        let load =
+            |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+
+        // The most significant bit is ignored, as required by X25519.
+        Self([
+            load(0) & LOW_51_BITS,
+            (load(6) >> 3) & LOW_51_BITS,
+            (load(12) >> 6) & LOW_51_BITS,
+            (load(19) >> 1) & LOW_51_BITS,
+            (load(24) >> 12) & LOW_51_BITS,
+        ])
+    }
+
+    fn to_bytes(self) -> [u8; X25519_KEY_SIZE] {
+        // Carry twice so that every limb fits in 51 bits.
+        let mut h = self.carry().carry().0;
+
+        // Compute the quotient of `h / p`, which is either zero or one, and subtract `q * p`.
+        let mut q = (h[0] + 19) >> 51;
+        for limb in &h[1..] {
+            q = (limb + q) >> 51;
+        }
+        h[0] += 19 * q;
+        for i in 0..4 {
+            h[i + 1] += h[i] >> 51;
+            h[i] &= LOW_51_BITS;
+        }
+        h[4] &= LOW_51_BITS;
+
+        let words = [
+            h[0] | (h[1] << 51),
+            (h[1] >> 13) | (h[2] << 38),
+            (h[2] >> 26) | (h[3] << 25),
+            (h[3] >> 39) | (h[4] << 12),
+        ];
+        let mut bytes = [0u8; X25519_KEY_SIZE];
+        for (chunk, word) in bytes.chunks_exact_mut(8).zip(words) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Propagates the carries so that every limb fits in 51 bits, except for a small excess in
+    /// the lowest limb.
+    fn carry(self) -> Self {
+        let mut h = self.0;
+        for i in 0..4 {
+            h[i + 1] += h[i] >> 51;
+            h[i] &= LOW_51_BITS;
+        }
+        h[0] += 19 * (h[4] >> 51);
+        h[4] &= LOW_51_BITS;
+        Self(h)
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        Self(core::array::from_fn(|i| self.0[i] + other.0[i])).carry()
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        // Add `4 * p` first so that the limbs never become negative.
+        const FOUR_P: [u64; 5] = [
+            0x1f_ffff_ffff_ffb4,
+            0x1f_ffff_ffff_fffc,
+            0x1f_ffff_ffff_fffc,
+            0x1f_ffff_ffff_fffc,
+            0x1f_ffff_ffff_fffc,
+        ];
+
+        Self(core::array::from_fn(|i| self.0[i] + FOUR_P[i] - other.0[i])).carry()
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        let [a0, a1, a2, a3, a4] = self.0.map(u128::from);
+        let [b0, b1, b2, b3, b4] = other.0.map(u128::from);
+        let [b1_19, b2_19, b3_19, b4_19] = [b1 * 19, b2 * 19, b3 * 19, b4 * 19];
+
+        let r0 = a0 * b0 + a1 * b4_19 + a2 * b3_19 + a3 * b2_19 + a4 * b1_19;
+        let r1 = a0 * b1 + a1 * b0 + a2 * b4_19 + a3 * b3_19 + a4 * b2_19;
+        let r2 = a0 * b2 + a1 * b1 + a2 * b0 + a3 * b4_19 + a4 * b3_19;
+        let r3 = a0 * b3 + a1 * b2 + a2 * b1 + a3 * b0 + a4 * b4_19;
+        let r4 = a0 * b4 + a1 * b3 + a2 * b2 + a3 * b1 + a4 * b0;
+
+        Self::reduce_wide([r0, r1, r2, r3, r4])
+    }
+
+    fn square(&self) -> Self {
+        self.mul(self)
+    }
+
+    fn mul_small(&self, n: u64) -> Self {
+        Self::reduce_wide(self.0.map(|limb| limb as u128 * n as u128))
+    }
+
+    fn reduce_wide(mut r: [u128; 5]) -> Self {
+        for i in 0..4 {
+            r[i + 1] += r[i] >> 51;
+            r[i] &= LOW_51_BITS as u128;
+        }
+        let carry = (r[4] >> 51) as u64;
+        r[4] &= LOW_51_BITS as u128;
+
+        let mut h = r.map(|limb| limb as u64);
+        h[0] += carry * 19;
+        h[1] += h[0] >> 51;
+        h[0] &= LOW_51_BITS;
+        Self(h)
+    }
+
+    /// Computes the multiplicative inverse as `self^(p - 2)`.
+    fn invert(&self) -> Self {
+        // The exponent `p - 2 = 2^255 - 21` has all of its 255 bits set, except bits 2 and 4.
+        let mut res = Self::ONE;
+        for bit in (0..255).rev() {
+            res = res.square();
+            if bit != 2 && bit != 4 {
+                res = res.mul(self);
+            }
+        }
+        res
+    }
+
+    /// Swaps the two elements if `swap` is one, in constant time.
+    fn cswap(swap: u64, lhs: &mut Self, rhs: &mut Self) {
+        let mask = 0u64.wrapping_sub(swap);
+        for (lhs, rhs) in lhs.0.iter_mut().zip(rhs.0.iter_mut()) {
+            let diff = mask & (*lhs ^ *rhs);
+            *lhs ^= diff;
+            *rhs ^= diff;
+        }
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    fn from_hex(hex: &str) -> [u8; X25519_KEY_SIZE] {
+        core::array::from_fn(|i| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap())
+    }
+
+    #[ktest]
+    fn x25519_rfc7748() {
+        let alice_private =
+            from_hex("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a");
+        let bob_private =
+            from_hex("5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb");
+
+        let alice_public = x25519_public_key(&alice_private);
+        let bob_public = x25519_public_key(&bob_private);
+        assert_eq!(
+            alice_public,
+            from_hex("8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a")
+        );
+        assert_eq!(
+            bob_public,
+            from_hex("de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f")
+        );
+
+        let shared = from_hex("4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742");
+        assert_eq!(x25519(&alice_private, &bob_public), shared);
+        assert_eq!(x25519(&bob_private, &alice_public), shared);
+    }
+}
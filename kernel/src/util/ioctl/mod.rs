@@ -260,6 +260,15 @@ impl<const MAGIC: u8, const NR: u8, const IS_MODERN: bool, D: PtrDataSpec>
     {
         f(SafePtr::new(current_userspace!(), self.arg))
     }
+
+    /// Returns the address of the ioctl argument in userspace.
+    ///
+    /// This is only for the ioctl commands whose declared argument types in Linux differ from the
+    /// types of the data that is actually passed (e.g., `TUNSETIFF` is declared with `int`, but it
+    /// takes a `struct ifreq`). Other ioctl commands should use the typed accessors instead.
+    pub fn arg_addr(&self) -> Vaddr {
+        self.arg
+    }
 }
 
 /// No input/output data.
@@ -310,7 +319,7 @@ macro_rules! impl_get_by_val_for {
 }
 
 // We can add more types as needed, e.g., `u32`, `i8`.
-impl_get_by_val_for! { i32 }
+impl_get_by_val_for! { i32 u64 }
 
 impl DataSpec for InData<[u8]> {
     const SIZE: Option<u16> = None;
@@ -389,7 +398,6 @@ impl<const MAGIC: u8, const NR: u8, const IS_MODERN: bool, T: Pod>
     Ioctl<MAGIC, NR, IS_MODERN, InOutData<T>>
 {
     /// Reads the ioctl argument from userspace.
-    #[expect(dead_code)]
     pub fn read(&self) -> Result<T> {
         self.with_data_ptr(|ptr| Ok(ptr.read()?))
     }
@@ -0,0 +1,51 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::family::CSocketAddrFamily;
+use crate::{net::socket::alg::AlgSocketAddr, prelude::*};
+
+/// Algorithm socket address.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/if_alg.h#L19>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub struct CSocketAddrAlg {
+    /// Address family (AF_ALG).
+    salg_family: u16,
+    /// Algorithm type.
+    salg_type: [u8; 14],
+    /// Required algorithm features.
+    salg_feat: u32,
+    /// Mask of the algorithm features.
+    salg_mask: u32,
+    /// Algorithm name.
+    salg_name: [u8; 64],
+}
+
+impl CSocketAddrAlg {
+    /// The minimum length of the address, which excludes the algorithm name.
+    pub(super) const MIN_LEN: usize = size_of::<Self>() - 64;
+}
+
+impl From<AlgSocketAddr> for CSocketAddrAlg {
+    fn from(value: AlgSocketAddr) -> Self {
+        Self {
+            salg_family: CSocketAddrFamily::AF_ALG as u16,
+            salg_type: value.type_,
+            salg_feat: value.feat,
+            salg_mask: value.mask,
+            salg_name: value.name,
+        }
+    }
+}
+
+impl From<CSocketAddrAlg> for AlgSocketAddr {
+    fn from(value: CSocketAddrAlg) -> Self {
+        debug_assert_eq!(value.salg_family, CSocketAddrFamily::AF_ALG as u16);
+        Self {
+            type_: value.salg_type,
+            feat: value.salg_feat,
+            mask: value.salg_mask,
+            name: value.salg_name,
+        }
+    }
+}
@@ -4,7 +4,14 @@ use core::cmp::min;
 
 use ostd::{mm::VmIo, task::Task};
 
-use super::{ip::CSocketAddrInet, netlink::CSocketAddrNetlink, unix, vsock::CSocketAddrVm};
+use super::{
+    alg::CSocketAddrAlg,
+    ip::{CSocketAddrInet, CSocketAddrInet6},
+    netlink::CSocketAddrNetlink,
+    packet::CSocketAddrLl,
+    unix,
+    vsock::CSocketAddrVm,
+};
 use crate::{current_userspace, net::socket::util::SocketAddr, prelude::*};
 
 /// Address family.
@@ -155,6 +162,13 @@ pub fn read_socket_addr_from_user(addr: Vaddr, addr_len: usize) -> Result<Socket
             let (addr, port) = CSocketAddrInet::from_first_bytes(storage.as_bytes()).into();
             SocketAddr::IPv4(addr, port)
         }
+        Ok(CSocketAddrFamily::AF_INET6) => {
+            if addr_len < size_of::<CSocketAddrInet6>() {
+                return_errno_with_message!(Errno::EINVAL, "the socket address length is too small");
+            }
+            let (addr, port) = CSocketAddrInet6::from_first_bytes(storage.as_bytes()).into();
+            SocketAddr::IPv6(addr, port)
+        }
         Ok(CSocketAddrFamily::AF_UNIX) => {
             let addr = unix::from_c_bytes(&storage.as_bytes()[..addr_len])?;
             SocketAddr::Unix(addr)
@@ -166,6 +180,13 @@ pub fn read_socket_addr_from_user(addr: Vaddr, addr_len: usize) -> Result<Socket
             let addr = CSocketAddrNetlink::from_first_bytes(storage.as_bytes());
             SocketAddr::Netlink(addr.into())
         }
+        Ok(CSocketAddrFamily::AF_PACKET) => {
+            if addr_len < size_of::<CSocketAddrLl>() {
+                return_errno_with_message!(Errno::EINVAL, "the socket address length is too small");
+            }
+            let addr = CSocketAddrLl::from_first_bytes(storage.as_bytes());
+            SocketAddr::Packet(addr.into())
+        }
         Ok(CSocketAddrFamily::AF_VSOCK) => {
             if addr_len < size_of::<CSocketAddrVm>() {
                 return_errno_with_message!(Errno::EINVAL, "the socket address length is too small");
@@ -173,6 +194,15 @@ pub fn read_socket_addr_from_user(addr: Vaddr, addr_len: usize) -> Result<Socket
             let addr = CSocketAddrVm::from_first_bytes(storage.as_bytes());
             SocketAddr::Vsock(addr.into())
         }
+        Ok(CSocketAddrFamily::AF_ALG) => {
+            // Like Linux, the algorithm name can be omitted or truncated, in which case the
+            // remaining bytes are zeros.
+            if addr_len < CSocketAddrAlg::MIN_LEN {
+                return_errno_with_message!(Errno::EINVAL, "the socket address length is too small");
+            }
+            let addr = CSocketAddrAlg::from_first_bytes(storage.as_bytes());
+            SocketAddr::Alg(addr.into())
+        }
         _ => {
             return_errno_with_message!(
                 Errno::EAFNOSUPPORT,
@@ -248,6 +278,11 @@ pub fn write_socket_addr_with_max_len(
             dest,
             max_len as usize,
         )?,
+        SocketAddr::IPv6(addr, port) => write_c_socket_address_util::<CSocketAddrInet6, _>(
+            (*addr, *port),
+            dest,
+            max_len as usize,
+        )?,
         SocketAddr::Unix(addr) => unix::into_c_bytes_and(addr, |bytes| {
             let written_len = min(bytes.len(), max_len as _);
             current_userspace!().write_bytes(dest, &bytes[..written_len])?;
@@ -259,6 +294,12 @@ pub fn write_socket_addr_with_max_len(
         SocketAddr::Vsock(addr) => {
             write_c_socket_address_util::<CSocketAddrVm, _>(*addr, dest, max_len as usize)?
         }
+        SocketAddr::Packet(addr) => {
+            write_c_socket_address_util::<CSocketAddrLl, _>(*addr, dest, max_len as usize)?
+        }
+        SocketAddr::Alg(addr) => {
+            write_c_socket_address_util::<CSocketAddrAlg, _>(*addr, dest, max_len as usize)?
+        }
     };
 
     Ok(actual_len as i32)
@@ -4,9 +4,13 @@ pub use family::{
     CSocketAddrFamily, read_socket_addr_from_user, write_socket_addr_to_user,
     write_socket_addr_with_max_len,
 };
+pub use ip::CSocketAddrInet;
+pub use packet::CSocketAddrLl;
 
+mod alg;
 mod family;
 mod ip;
 mod netlink;
+mod packet;
 mod unix;
 mod vsock;
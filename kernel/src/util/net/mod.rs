@@ -5,8 +5,8 @@ mod options;
 mod socket;
 
 pub use addr::{
-    CSocketAddrFamily, read_socket_addr_from_user, write_socket_addr_to_user,
-    write_socket_addr_with_max_len,
+    CSocketAddrFamily, CSocketAddrInet, CSocketAddrLl, read_socket_addr_from_user,
+    write_socket_addr_to_user, write_socket_addr_with_max_len,
 };
 pub use options::{CSocketOptionLevel, new_raw_socket_option};
-pub use socket::{CUserMsgHdr, Protocol, SOCK_TYPE_MASK, SockFlags, SockType};
+pub use socket::{CUserMmsgHdr, CUserMsgHdr, Protocol, SOCK_TYPE_MASK, SockFlags, SockType};
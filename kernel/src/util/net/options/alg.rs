@@ -0,0 +1,35 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::RawSocketOption;
+use crate::{
+    impl_raw_sock_option_set_only,
+    net::socket::alg::options::{AlgSetAeadAuthsize, AlgSetKey},
+    prelude::*,
+    util::net::options::SocketOption,
+};
+
+/// Socket options for algorithm sockets.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/include/uapi/linux/if_alg.h#L48>.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, TryFromInt)]
+#[expect(non_camel_case_types)]
+#[expect(clippy::upper_case_acronyms)]
+pub enum CAlgOptionName {
+    SET_KEY = 1,
+    SET_AEAD_AUTHSIZE = 5,
+    SET_DRBG_ENTROPY = 6,
+    SET_KEY_BY_KEY_SERIAL = 7,
+}
+
+pub fn new_alg_option(name: i32) -> Result<Box<dyn RawSocketOption>> {
+    let name = CAlgOptionName::try_from(name).map_err(|_| Errno::ENOPROTOOPT)?;
+    match name {
+        CAlgOptionName::SET_KEY => Ok(Box::new(AlgSetKey::new())),
+        CAlgOptionName::SET_AEAD_AUTHSIZE => Ok(Box::new(AlgSetAeadAuthsize::new())),
+        _ => return_errno_with_message!(Errno::ENOPROTOOPT, "unsupported alg option"),
+    }
+}
+
+impl_raw_sock_option_set_only!(AlgSetKey);
+impl_raw_sock_option_set_only!(AlgSetAeadAuthsize);
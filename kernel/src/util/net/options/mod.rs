@@ -51,15 +51,25 @@
 //! At the syscall level, the interface is unified for all options and does not need to be modified.
 //!
 
+use alg::new_alg_option;
 use ip::new_ip_option;
+use ipv6::new_ipv6_option;
 use netlink::new_netlink_option;
+use packet::new_packet_option;
+use raw::new_raw_option;
+use tls::new_tls_option;
 
 use crate::{net::socket::options::SocketOption, prelude::*};
 
+mod alg;
 mod ip;
+mod ipv6;
 mod netlink;
+mod packet;
+mod raw;
 mod socket;
 mod tcp;
+mod tls;
 mod utils;
 
 use self::{socket::new_socket_option, tcp::new_tcp_option};
@@ -167,8 +177,13 @@ pub fn new_raw_socket_option(
     match level {
         CSocketOptionLevel::SOL_SOCKET => new_socket_option(name),
         CSocketOptionLevel::SOL_IP => new_ip_option(name),
+        CSocketOptionLevel::SOL_IPV6 => new_ipv6_option(name),
         CSocketOptionLevel::SOL_TCP => new_tcp_option(name),
+        CSocketOptionLevel::SOL_RAW => new_raw_option(name),
+        CSocketOptionLevel::SOL_PACKET => new_packet_option(name),
         CSocketOptionLevel::SOL_NETLINK => new_netlink_option(name),
+        CSocketOptionLevel::SOL_TLS => new_tls_option(name),
+        CSocketOptionLevel::SOL_ALG => new_alg_option(name),
         _ => return_errno_with_message!(Errno::EOPNOTSUPP, "unsupported option level"),
     }
 }
@@ -188,5 +203,8 @@ pub enum CSocketOptionLevel {
     SOL_UDP = 17,
     SOL_IPV6 = 41,
     SOL_RAW = 255,
+    SOL_PACKET = 263,
     SOL_NETLINK = 270,
+    SOL_ALG = 279,
+    SOL_TLS = 282,
 }
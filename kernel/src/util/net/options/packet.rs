@@ -0,0 +1,107 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use ostd::mm::VmIo;
+
+use super::RawSocketOption;
+use crate::{
+    current_userspace, impl_raw_sock_option_get_only, impl_raw_sock_option_set_only,
+    impl_raw_socket_option,
+    net::socket::packet::options::{
+        PacketAddMembership, PacketAuxdata, PacketDropMembership, PacketHdrLen, PacketReserve,
+        PacketRxRing, PacketStatistics, PacketVersion,
+    },
+    prelude::*,
+    util::net::options::SocketOption,
+};
+
+/// Socket options for packet sockets.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/if_packet.h#L49>.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, TryFromInt)]
+#[expect(non_camel_case_types)]
+#[expect(clippy::upper_case_acronyms)]
+pub enum CPacketOptionName {
+    ADD_MEMBERSHIP = 1,
+    DROP_MEMBERSHIP = 2,
+    RECV_OUTPUT = 3,
+    RX_RING = 5,
+    STATISTICS = 6,
+    COPY_THRESH = 7,
+    AUXDATA = 8,
+    ORIGDEV = 9,
+    VERSION = 10,
+    HDRLEN = 11,
+    RESERVE = 12,
+    TX_RING = 13,
+    LOSS = 14,
+    VNET_HDR = 15,
+    TX_TIMESTAMP = 16,
+    TIMESTAMP = 17,
+    FANOUT = 18,
+    TX_HAS_OFF = 19,
+    QDISC_BYPASS = 20,
+    ROLLOVER_STATS = 21,
+    FANOUT_DATA = 22,
+    IGNORE_OUTGOING = 23,
+}
+
+pub fn new_packet_option(name: i32) -> Result<Box<dyn RawSocketOption>> {
+    let name = CPacketOptionName::try_from(name).map_err(|_| Errno::ENOPROTOOPT)?;
+    match name {
+        CPacketOptionName::ADD_MEMBERSHIP => Ok(Box::new(PacketAddMembership::new())),
+        CPacketOptionName::DROP_MEMBERSHIP => Ok(Box::new(PacketDropMembership::new())),
+        CPacketOptionName::RX_RING => Ok(Box::new(PacketRxRing::new())),
+        CPacketOptionName::STATISTICS => Ok(Box::new(PacketStatistics::new())),
+        CPacketOptionName::AUXDATA => Ok(Box::new(PacketAuxdata::new())),
+        CPacketOptionName::VERSION => Ok(Box::new(PacketVersion::new())),
+        CPacketOptionName::HDRLEN => Ok(Box::new(PacketHdrLen::new())),
+        CPacketOptionName::RESERVE => Ok(Box::new(PacketReserve::new())),
+        _ => return_errno_with_message!(Errno::ENOPROTOOPT, "unsupported packet option"),
+    }
+}
+
+impl_raw_sock_option_set_only!(PacketAddMembership);
+impl_raw_sock_option_set_only!(PacketDropMembership);
+impl_raw_sock_option_set_only!(PacketRxRing);
+impl_raw_sock_option_get_only!(PacketStatistics);
+impl_raw_socket_option!(PacketAuxdata);
+impl_raw_socket_option!(PacketVersion);
+impl_raw_socket_option!(PacketReserve);
+
+// PACKET_HDRLEN is a read-only option, but `getsockopt` reads the version from the option value
+// and writes the header length of the version back. Therefore, we manually implement
+// `RawSocketOption` for it.
+impl RawSocketOption for PacketHdrLen {
+    fn read_from_user(&mut self, _addr: Vaddr, _max_len: u32) -> Result<()> {
+        return_errno_with_message!(Errno::ENOPROTOOPT, "the option is getter-only");
+    }
+
+    fn write_to_user(&self, addr: Vaddr, max_len: &mut u32) -> Result<usize> {
+        if (*max_len as usize) < size_of::<i32>() {
+            return_errno_with_message!(Errno::EINVAL, "max_len is too short");
+        }
+
+        // Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/net/packet/af_packet.c#L4066>.
+        let hdrlen: i32 = match current_userspace!().read_val::<i32>(addr)? {
+            TPACKET_V1 | TPACKET_V2 => 32,
+            TPACKET_V3 => 48,
+            _ => return_errno_with_message!(Errno::EINVAL, "the version is invalid"),
+        };
+
+        current_userspace!().write_val(addr, &hdrlen)?;
+        Ok(size_of::<i32>())
+    }
+
+    fn as_sock_option_mut(&mut self) -> &mut dyn SocketOption {
+        self
+    }
+
+    fn as_sock_option(&self) -> &dyn SocketOption {
+        self
+    }
+}
+
+const TPACKET_V1: i32 = 0;
+const TPACKET_V2: i32 = 1;
+const TPACKET_V3: i32 = 2;
@@ -4,10 +4,13 @@ use ostd::mm::VmIo;
 
 use super::RawSocketOption;
 use crate::{
-    current_userspace, impl_raw_sock_option_get_only, impl_raw_socket_option,
+    current_userspace, impl_raw_sock_option_get_only, impl_raw_sock_option_set_only,
+    impl_raw_socket_option,
     net::socket::options::{
-        AcceptConn, Broadcast, Error, KeepAlive, Linger, PassCred, PeerCred, PeerGroups, Priority,
-        RecvBuf, RecvBufForce, ReuseAddr, ReusePort, SendBuf, SendBufForce, SocketOption,
+        AcceptConn, AttachBpf, AttachFilter, BindToDevice, Broadcast, DetachFilter, Error,
+        KeepAlive, Linger, LockFilter, PassCred, PeerCred, PeerGroups, Priority, RecvBuf,
+        RecvBufForce, ReuseAddr, ReusePort, SendBuf, SendBufForce, SocketOption, Timestamp,
+        TimestampNs, Timestamping, Zerocopy,
     },
     prelude::*,
     process::Gid,
@@ -38,13 +41,20 @@ enum CSocketOptionName {
     REUSEPORT = 15,
     PASSCRED = 16,
     PEERCRED = 17,
+    BINDTODEVICE = 25,
     ATTACH_FILTER = 26,
     DETACH_FILTER = 27,
+    TIMESTAMP_OLD = 29,
     ACCPETCONN = 30,
     PEERSEC = 31,
     SNDBUFFORCE = 32,
     RCVBUFFORCE = 33,
+    TIMESTAMPNS_OLD = 35,
+    TIMESTAMPING_OLD = 37,
+    LOCK_FILTER = 44,
+    ATTACH_BPF = 50,
     PEERGROUPS = 59,
+    ZEROCOPY = 60,
     RCVTIMEO_NEW = 66,
     SNDTIMEO_NEW = 67,
 }
@@ -63,10 +73,19 @@ pub fn new_socket_option(name: i32) -> Result<Box<dyn RawSocketOption>> {
         CSocketOptionName::REUSEPORT => Ok(Box::new(ReusePort::new())),
         CSocketOptionName::PASSCRED => Ok(Box::new(PassCred::new())),
         CSocketOptionName::PEERCRED => Ok(Box::new(PeerCred::new())),
+        CSocketOptionName::BINDTODEVICE => Ok(Box::new(BindToDevice::new())),
+        CSocketOptionName::ATTACH_FILTER => Ok(Box::new(AttachFilter::new())),
+        CSocketOptionName::DETACH_FILTER => Ok(Box::new(DetachFilter::new())),
+        CSocketOptionName::TIMESTAMP_OLD => Ok(Box::new(Timestamp::new())),
         CSocketOptionName::ACCPETCONN => Ok(Box::new(AcceptConn::new())),
         CSocketOptionName::SNDBUFFORCE => Ok(Box::new(SendBufForce::new())),
         CSocketOptionName::RCVBUFFORCE => Ok(Box::new(RecvBufForce::new())),
+        CSocketOptionName::TIMESTAMPNS_OLD => Ok(Box::new(TimestampNs::new())),
+        CSocketOptionName::TIMESTAMPING_OLD => Ok(Box::new(Timestamping::new())),
+        CSocketOptionName::LOCK_FILTER => Ok(Box::new(LockFilter::new())),
+        CSocketOptionName::ATTACH_BPF => Ok(Box::new(AttachBpf::new())),
         CSocketOptionName::PEERGROUPS => Ok(Box::new(PeerGroups::new())),
+        CSocketOptionName::ZEROCOPY => Ok(Box::new(Zerocopy::new())),
         _ => return_errno_with_message!(Errno::ENOPROTOOPT, "unsupported socket-level option"),
     }
 }
@@ -85,6 +104,15 @@ impl_raw_sock_option_get_only!(PeerCred);
 impl_raw_sock_option_get_only!(AcceptConn);
 impl_raw_socket_option!(SendBufForce);
 impl_raw_socket_option!(RecvBufForce);
+impl_raw_sock_option_set_only!(AttachFilter);
+impl_raw_sock_option_set_only!(DetachFilter);
+impl_raw_sock_option_set_only!(AttachBpf);
+impl_raw_socket_option!(LockFilter);
+impl_raw_socket_option!(Zerocopy);
+impl_raw_socket_option!(Timestamp);
+impl_raw_socket_option!(TimestampNs);
+impl_raw_socket_option!(Timestamping);
+impl_raw_socket_option!(BindToDevice);
 
 // SO_PEERGROUPS is a read-only option. However, calling setsockopt on SO_PEERGROUPS will return EINVAL
 // instead of ENOPROTOOPT like other options. Therefore, we manually implement `RawSocketOption` for it.
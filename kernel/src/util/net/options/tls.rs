@@ -0,0 +1,35 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::RawSocketOption;
+use crate::{
+    impl_raw_socket_option,
+    net::socket::ip::stream_options::{TlsRx, TlsTx},
+    prelude::*,
+    util::net::options::SocketOption,
+};
+
+/// Socket options for TLS sockets.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/tls.h#L40>.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, TryFromInt)]
+#[expect(non_camel_case_types)]
+#[expect(clippy::upper_case_acronyms)]
+pub enum CTlsOptionName {
+    TX = 1,
+    RX = 2,
+    TX_ZEROCOPY_RO = 3,
+    RX_EXPECT_NO_PAD = 4,
+}
+
+pub fn new_tls_option(name: i32) -> Result<Box<dyn RawSocketOption>> {
+    let name = CTlsOptionName::try_from(name).map_err(|_| Errno::ENOPROTOOPT)?;
+    match name {
+        CTlsOptionName::TX => Ok(Box::new(TlsTx::new())),
+        CTlsOptionName::RX => Ok(Box::new(TlsRx::new())),
+        _ => return_errno_with_message!(Errno::ENOPROTOOPT, "unsupported tls option"),
+    }
+}
+
+impl_raw_socket_option!(TlsTx);
+impl_raw_socket_option!(TlsRx);
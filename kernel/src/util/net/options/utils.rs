@@ -2,14 +2,25 @@
 
 use core::{num::NonZeroU8, time::Duration};
 
-use ostd::mm::VmIo;
+use aster_bigtcp::wire::Ipv4Address;
+use ostd::{mm::VmIo, task::Task};
 
 use crate::{
+    bpf::{BpfProg, BpfProgFile},
     current_userspace,
+    fs::file::file_table::get_file_fast,
     net::socket::{
-        ip::{options::IpTtl, stream_options::CongestionControl},
+        alg::options::{AeadAuthSize, AlgKey},
+        ip::{
+            options::{IpMembershipRequest, IpTtl},
+            stream_options::{CongestionControl, TlsCryptoInfo},
+        },
+        packet::{
+            CTpacketReq3,
+            options::{CPacketMreq, CTpacketStats},
+        },
         unix::CUserCred,
-        util::LingerOption,
+        util::{CSockFilter, LingerOption, SocketFilter, TimestampingFlags},
     },
     prelude::*,
 };
@@ -121,6 +132,31 @@ impl WriteToUser for IpTtl {
     }
 }
 
+impl ReadFromUser for IpMembershipRequest {
+    fn read_from_user(addr: Vaddr, max_len: u32) -> Result<Self> {
+        // Like Linux, `struct ip_mreqn` is read if the length allows, otherwise `struct ip_mreq`
+        // is read, which does not contain the interface index.
+        let c_mreqn = if (max_len as usize) >= size_of::<CIpMreqn>() {
+            current_userspace!().read_val::<CIpMreqn>(addr)?
+        } else if (max_len as usize) >= size_of::<CIpMreq>() {
+            let c_mreq = current_userspace!().read_val::<CIpMreq>(addr)?;
+            CIpMreqn {
+                imr_multiaddr: c_mreq.imr_multiaddr,
+                imr_address: c_mreq.imr_interface,
+                imr_ifindex: 0,
+            }
+        } else {
+            return_errno_with_message!(Errno::EINVAL, "max_len is too short");
+        };
+
+        Ok(IpMembershipRequest {
+            group_addr: Ipv4Address::from(c_mreqn.imr_multiaddr),
+            interface_addr: Ipv4Address::from(c_mreqn.imr_address),
+            interface_index: c_mreqn.imr_ifindex as u32,
+        })
+    }
+}
+
 impl WriteToUser for Option<Error> {
     fn write_to_user(&self, addr: Vaddr, max_len: u32) -> Result<usize> {
         let write_len = size_of::<i32>();
@@ -178,7 +214,9 @@ impl ReadFromUser for CongestionControl {
 
         current_userspace!().read_bytes(addr, dst.as_mut())?;
 
-        let name = core::str::from_utf8(dst)
+        // Like Linux, the name ends at the first null byte, if any.
+        let name_len = dst.iter().position(|&byte| byte == 0).unwrap_or(dst.len());
+        let name = core::str::from_utf8(&dst[..name_len])
             .map_err(|_| Error::with_message(Errno::ENOENT, "non-UTF8 congestion name"))?;
         CongestionControl::new(name)
     }
@@ -200,6 +238,50 @@ impl WriteToUser for CongestionControl {
     }
 }
 
+const IFNAMSIZ: u32 = 16;
+
+// Strings are used for interface names (`SO_BINDTODEVICE`), where an empty name means no
+// interface, and for upper layer protocol names (`TCP_ULP`), which have the same size limit.
+
+impl ReadFromUser for String {
+    fn read_from_user(addr: Vaddr, max_len: u32) -> Result<Self> {
+        let mut bytes = [0; IFNAMSIZ as usize];
+
+        let dst = {
+            let read_len = (IFNAMSIZ - 1).min(max_len) as usize;
+            &mut bytes[..read_len]
+        };
+
+        current_userspace!().read_bytes(addr, dst.as_mut())?;
+
+        // Like Linux, the name ends at the first null byte, if any.
+        let name_len = dst.iter().position(|&byte| byte == 0).unwrap_or(dst.len());
+        let name = core::str::from_utf8(&dst[..name_len])
+            .map_err(|_| Error::with_message(Errno::ENODEV, "non-UTF8 interface name"))?;
+        Ok(name.to_string())
+    }
+}
+
+impl WriteToUser for String {
+    fn write_to_user(&self, addr: Vaddr, max_len: u32) -> Result<usize> {
+        if self.is_empty() {
+            return Ok(0);
+        }
+
+        // The name is written with the null terminator.
+        let write_len = self.len() + 1;
+        if (max_len as usize) < write_len {
+            return_errno_with_message!(Errno::EINVAL, "max_len is too short");
+        }
+
+        let mut bytes = self.as_bytes().to_vec();
+        bytes.push(0);
+        current_userspace!().write_bytes(addr, &bytes)?;
+
+        Ok(write_len)
+    }
+}
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Pod)]
 struct CLinger {
@@ -225,6 +307,21 @@ impl From<CLinger> for LingerOption {
     }
 }
 
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CIpMreq {
+    imr_multiaddr: [u8; 4], // IP multicast address of group
+    imr_interface: [u8; 4], // local IP address of interface
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CIpMreqn {
+    imr_multiaddr: [u8; 4], // IP multicast address of group
+    imr_address: [u8; 4],   // local IP address of interface
+    imr_ifindex: i32,       // interface index
+}
+
 impl WriteToUser for CUserCred {
     fn write_to_user(&self, addr: Vaddr, max_len: u32) -> Result<usize> {
         let write_len = size_of::<CUserCred>();
@@ -238,3 +335,189 @@ impl WriteToUser for CUserCred {
         Ok(write_len)
     }
 }
+
+impl ReadFromUser for SocketFilter {
+    fn read_from_user(addr: Vaddr, max_len: u32) -> Result<Self> {
+        if (max_len as usize) < size_of::<CSockFprog>() {
+            return_errno_with_message!(Errno::EINVAL, "max_len is too short");
+        }
+
+        let c_fprog = current_userspace!().read_val::<CSockFprog>(addr)?;
+
+        let mut insns = vec![CSockFilter::new_zeroed(); c_fprog.len as usize];
+        current_userspace!().read_slice(c_fprog.filter as Vaddr, &mut insns)?;
+
+        SocketFilter::new(insns)
+    }
+}
+
+impl ReadFromUser for Arc<BpfProg> {
+    fn read_from_user(addr: Vaddr, max_len: u32) -> Result<Self> {
+        let fd = i32::read_from_user(addr, max_len)?;
+
+        let current = Task::current().unwrap();
+        let mut file_table = current.as_thread_local().unwrap().borrow_file_table_mut();
+        let file = get_file_fast!(&mut file_table, fd);
+        let Some(prog_file) = file.downcast_ref::<BpfProgFile>() else {
+            return_errno_with_message!(Errno::EINVAL, "the file is not a BPF program");
+        };
+
+        Ok(prog_file.prog().clone())
+    }
+}
+
+/// The program of a socket filter.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/filter.h#L31>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CSockFprog {
+    len: u16,
+    _pad: [u8; 6],
+    filter: u64,
+}
+
+impl ReadFromUser for TimestampingFlags {
+    fn read_from_user(addr: Vaddr, max_len: u32) -> Result<Self> {
+        // Like Linux, `struct so_timestamping` is read if the length matches, otherwise an integer
+        // is read, which does not contain the PHC index.
+        let flags = if (max_len as usize) == size_of::<CSoTimestamping>() {
+            let c_timestamping = current_userspace!().read_val::<CSoTimestamping>(addr)?;
+            c_timestamping.flags
+        } else {
+            i32::read_from_user(addr, max_len)?
+        };
+
+        TimestampingFlags::from_bits(flags as u32)
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid timestamping flags"))
+    }
+}
+
+impl WriteToUser for TimestampingFlags {
+    fn write_to_user(&self, addr: Vaddr, max_len: u32) -> Result<usize> {
+        let c_timestamping = CSoTimestamping {
+            flags: self.bits() as i32,
+            bind_phc: 0,
+        };
+
+        // Like Linux, the option is truncated if the buffer is too short.
+        let write_len = size_of::<CSoTimestamping>().min(max_len as usize);
+        current_userspace!().write_bytes(addr, &c_timestamping.as_bytes()[..write_len])?;
+
+        Ok(write_len)
+    }
+}
+
+/// The argument of `SO_TIMESTAMPING`.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/net_tstamp.h#L60>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CSoTimestamping {
+    flags: i32,
+    bind_phc: i32,
+}
+
+impl ReadFromUser for TlsCryptoInfo {
+    fn read_from_user(addr: Vaddr, max_len: u32) -> Result<Self> {
+        // The length is checked against the size of the cipher-specific structure later.
+        if (max_len as usize) > TlsCryptoInfo::MAX_LEN {
+            return_errno_with_message!(Errno::EINVAL, "max_len is too long");
+        }
+
+        let mut bytes = vec![0u8; max_len as usize];
+        current_userspace!().read_bytes(addr, bytes.as_mut_slice())?;
+
+        TlsCryptoInfo::from_bytes(&bytes)
+    }
+}
+
+impl WriteToUser for TlsCryptoInfo {
+    fn write_to_user(&self, addr: Vaddr, max_len: u32) -> Result<usize> {
+        let bytes = self.to_bytes();
+
+        // Like Linux, only the common header is written if the buffer is exactly large enough for
+        // it, which lets userspace query the cipher first.
+        let write_len = if max_len as usize == TlsCryptoInfo::HEADER_LEN {
+            TlsCryptoInfo::HEADER_LEN
+        } else if (max_len as usize) < bytes.len() {
+            return_errno_with_message!(Errno::EINVAL, "max_len is too short");
+        } else {
+            bytes.len()
+        };
+
+        current_userspace!().write_bytes(addr, &bytes[..write_len])?;
+
+        Ok(write_len)
+    }
+}
+
+impl ReadFromUser for AlgKey {
+    fn read_from_user(addr: Vaddr, max_len: u32) -> Result<Self> {
+        if (max_len as usize) > AlgKey::MAX_LEN {
+            return_errno_with_message!(Errno::ENOMEM, "the key is too long");
+        }
+
+        let mut key = vec![0u8; max_len as usize];
+        current_userspace!().read_bytes(addr, key.as_mut_slice())?;
+
+        Ok(AlgKey(key))
+    }
+}
+
+impl ReadFromUser for AeadAuthSize {
+    fn read_from_user(_addr: Vaddr, max_len: u32) -> Result<Self> {
+        Ok(AeadAuthSize(max_len))
+    }
+}
+
+impl ReadFromUser for CTpacketReq3 {
+    fn read_from_user(addr: Vaddr, max_len: u32) -> Result<Self> {
+        // TODO: Support `struct tpacket_req`, which is used by `TPACKET_V1` and `TPACKET_V2`.
+        if (max_len as usize) < size_of::<CTpacketReq3>() {
+            return_errno_with_message!(Errno::EINVAL, "max_len is too short");
+        }
+
+        Ok(current_userspace!().read_val::<CTpacketReq3>(addr)?)
+    }
+}
+
+impl ReadFromUser for CPacketMreq {
+    fn read_from_user(addr: Vaddr, max_len: u32) -> Result<Self> {
+        if (max_len as usize) < size_of::<CPacketMreq>() {
+            return_errno_with_message!(Errno::EINVAL, "max_len is too short");
+        }
+
+        let mut c_mreq = current_userspace!().read_val::<CPacketMreq>(addr)?;
+
+        let alen = c_mreq.mr_alen as usize;
+        if alen > c_mreq.mr_address.len() {
+            return_errno_with_message!(Errno::EINVAL, "the address is too long");
+        }
+        // The bytes after the address are ignored.
+        c_mreq.mr_address[alen..].fill(0);
+
+        Ok(c_mreq)
+    }
+}
+
+impl WriteToUser for CTpacketStats {
+    fn write_to_user(&self, addr: Vaddr, max_len: u32) -> Result<usize> {
+        let stats = [
+            self.tp_packets,
+            self.tp_drops,
+            self.tp_freeze_q_cnt.unwrap_or(0),
+        ];
+        let stats_len = if self.tp_freeze_q_cnt.is_some() {
+            size_of_val(&stats)
+        } else {
+            size_of_val(&stats[..2])
+        };
+
+        // Like Linux, the statistics are truncated if the buffer is too short.
+        let write_len = stats_len.min(max_len as usize);
+        current_userspace!().write_bytes(addr, &stats.as_bytes()[..write_len])?;
+
+        Ok(write_len)
+    }
+}
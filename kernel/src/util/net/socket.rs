@@ -48,7 +48,7 @@ pub enum Protocol {
 /// From <https://elixir.bootlin.com/linux/v6.0.9/source/include/linux/net.h>.
 #[repr(i32)]
 #[expect(non_camel_case_types)]
-#[derive(Debug, Clone, Copy, TryFromInt)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
 pub enum SockType {
     /// Stream socket
     SOCK_STREAM = 1,
@@ -97,6 +97,25 @@ pub struct CUserMsgHdr {
     pub msg_flags: u32,
 }
 
+/// A message header used by `sendmmsg` and `recvmmsg`.
+#[repr(C)]
+#[padding_struct]
+#[derive(Debug, Clone, Copy, Pod)]
+pub struct CUserMmsgHdr {
+    /// The message header
+    pub msg_hdr: CUserMsgHdr,
+    /// The # of bytes sent or received for the message
+    pub msg_len: u32,
+}
+
+impl CUserMmsgHdr {
+    /// The maximum number of messages that can be sent or received in a single system call.
+    ///
+    /// Like Linux, a larger number of messages is silently truncated to this value.
+    /// Reference: <https://elixir.bootlin.com/linux/v6.16/source/net/socket.c#L2700>.
+    pub const MAX_COUNT: usize = MAX_IO_VECTOR_LENGTH;
+}
+
 impl CUserMsgHdr {
     pub fn read_socket_addr_from_user(&self) -> Result<Option<SocketAddr>> {
         if self.msg_name == 0 {
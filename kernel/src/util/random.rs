@@ -1,24 +1,123 @@
 // SPDX-License-Identifier: MPL-2.0
 
+//! The cryptographically secure random number generator of the kernel.
+//!
+//! The generator is seeded with the hardware randomness at boot. Afterwards, the randomness from
+//! the hardware random number generators and the writes to `/dev/random` is mixed into an input
+//! pool. Like Linux since 5.18, the generator is reseeded from the pool once the reseed interval
+//! has elapsed, so that a leaked state of the generator does not reveal the random bytes generated
+//! after the next reseed.
+
+use core::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+
+use ostd::timer::Jiffies;
 use rand::{RngCore, SeedableRng, rngs::StdRng};
 use spin::Once;
 
-use crate::prelude::*;
+use crate::{prelude::*, util::crypto::sha256::Sha256};
+
+static RNG: Once<SpinLock<Crng>> = Once::new();
+
+/// The size of the input pool in bits.
+pub const POOL_SIZE_BITS: u32 = 256;
 
-static RNG: Once<SpinLock<StdRng>> = Once::new();
+/// The minimum interval between two reseeds in seconds.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/char/random.c>.
+static MIN_RESEED_SECS: AtomicU32 = AtomicU32::new(60);
+
+/// The threshold of `/dev/random` for waking up writers in bits.
+///
+/// Like Linux since 5.18, the threshold has no effect because writers are never blocked.
+static WRITE_WAKEUP_THRESHOLD: AtomicU32 = AtomicU32::new(POOL_SIZE_BITS);
+
+struct Crng {
+    rng: StdRng,
+    /// The input pool, which accumulates the randomness until the next reseed.
+    pool: Sha256,
+    /// The time of the last reseed since boot.
+    last_reseed: Duration,
+}
+
+impl Crng {
+    fn reseed_if_needed(&mut self) {
+        let now = Jiffies::elapsed().as_duration();
+        if now.saturating_sub(self.last_reseed) < min_reseed_interval() {
+            return;
+        }
+
+        // The output of the current generator is mixed in, so that the new seed is never
+        // weaker than the current state.
+        let mut output = [0u8; 32];
+        self.rng.fill_bytes(&mut output);
+        let mut pool = core::mem::take(&mut self.pool);
+        pool.update(&output);
+
+        self.rng = StdRng::from_seed(pool.finalize());
+        self.last_reseed = now;
+    }
+}
 
 /// Fill `dest` with random bytes.
 ///
 /// It's cryptographically secure, as documented in [`rand::rngs::StdRng`].
 pub fn getrandom(dst: &mut [u8]) {
-    RNG.get().unwrap().lock().fill_bytes(dst);
+    let mut crng = RNG.get().unwrap().lock();
+    crng.reseed_if_needed();
+    crng.rng.fill_bytes(dst);
+}
+
+/// Mixes the data into the input pool, which is used in the next reseed.
+pub fn add_randomness(data: &[u8]) {
+    RNG.get().unwrap().lock().pool.update(data);
+}
+
+/// Returns the entropy that is available in bits.
+///
+/// Like Linux since 5.18, the entropy is never depleted once the generator is seeded.
+pub fn entropy_avail() -> u32 {
+    POOL_SIZE_BITS
+}
+
+/// Returns the minimum interval between two reseeds in seconds.
+pub fn min_reseed_secs() -> u32 {
+    MIN_RESEED_SECS.load(Ordering::Relaxed)
+}
+
+/// Sets the minimum interval between two reseeds in seconds.
+pub fn set_min_reseed_secs(secs: u32) {
+    MIN_RESEED_SECS.store(secs, Ordering::Relaxed);
+}
+
+/// Returns the minimum interval between two reseeds.
+pub fn min_reseed_interval() -> Duration {
+    Duration::from_secs(min_reseed_secs().into())
+}
+
+/// Returns the threshold of `/dev/random` for waking up writers in bits.
+pub fn write_wakeup_threshold() -> u32 {
+    WRITE_WAKEUP_THRESHOLD.load(Ordering::Relaxed)
+}
+
+/// Sets the threshold of `/dev/random` for waking up writers in bits.
+pub fn set_write_wakeup_threshold(bits: u32) {
+    WRITE_WAKEUP_THRESHOLD.store(bits, Ordering::Relaxed);
 }
 
 pub fn init() {
     // The seed used to initialize the RNG is required to be secure and unpredictable.
     let seed = get_random_seed();
 
-    RNG.call_once(|| SpinLock::new(StdRng::from_seed(seed)));
+    RNG.call_once(|| {
+        SpinLock::new(Crng {
+            rng: StdRng::from_seed(seed),
+            pool: Sha256::new(),
+            last_reseed: Jiffies::elapsed().as_duration(),
+        })
+    });
 }
 
 #[cfg(target_arch = "x86_64")]
@@ -11,6 +11,10 @@
 //! [`VdsoData`] instance with necessary time-related information, and a Virtual Memory Object
 //! ([`Vmo`]) that encapsulates both the data and the vDSO routines. The VMO is intended to be
 //! mapped into the address space of every user space process for efficient access.
+//!
+//! Besides the time-related routines, the vDSO library provides `getcpu`. It does not depend on
+//! the vDSO data. Instead, on x86-64, it reads the current CPU from a per-CPU segment descriptor
+//! or from the `IA32_TSC_AUX` register, both of which are set up by OSTD.
 
 use alloc::sync::Arc;
 use core::{mem::ManuallyDrop, time::Duration};
@@ -30,13 +34,13 @@ use crate::{
     time::{
         START_TIME, SystemTime,
         clocks::MonotonicClock,
+        ntp, sleep_time,
         timer::{Timeout, TimerGuard},
     },
     vm::vmo::{Vmo, VmoOptions},
 };
 
-const CLOCK_TAI: usize = 11;
-const VDSO_BASES: usize = CLOCK_TAI + 1;
+const VDSO_BASES: usize = ClockId::CLOCK_TAI as usize + 1;
 const DEFAULT_CLOCK_MODE: VdsoClockMode = VdsoClockMode::Tsc;
 
 static START_SECS_COUNT: Once<u64> = Once::new();
@@ -104,11 +108,12 @@ struct VdsoData {
     arch_data: ArchVdsoData,
 }
 
-const HIGH_RES_CLOCK_IDS: [ClockId; 4] = [
+const HIGH_RES_CLOCK_IDS: [ClockId; 5] = [
     ClockId::CLOCK_REALTIME,
     ClockId::CLOCK_MONOTONIC,
     ClockId::CLOCK_MONOTONIC_RAW,
     ClockId::CLOCK_BOOTTIME,
+    ClockId::CLOCK_TAI,
 ];
 
 const COARSE_RES_CLOCK_IDS: [ClockId; 2] = [
@@ -163,15 +168,11 @@ impl VdsoData {
     fn update_high_res_instant(&mut self, instant: Instant, instant_cycles: u64) {
         self.last_cycles = instant_cycles;
         for clock_id in HIGH_RES_CLOCK_IDS {
-            let secs = if clock_id == ClockId::CLOCK_REALTIME {
-                instant.secs() + START_SECS_COUNT.get().unwrap()
-            } else {
-                instant.secs()
-            };
+            let instant = clock_instant(clock_id, instant);
 
             self.update_clock_instant(
                 clock_id as usize,
-                secs,
+                instant.secs(),
                 (instant.nanos() as u64) << self.shift as u64,
             );
         }
@@ -179,16 +180,26 @@ impl VdsoData {
 
     fn update_coarse_res_instant(&mut self, instant: Instant) {
         for clock_id in COARSE_RES_CLOCK_IDS {
-            let secs = if clock_id == ClockId::CLOCK_REALTIME_COARSE {
-                instant.secs() + START_SECS_COUNT.get().unwrap()
-            } else {
-                instant.secs()
-            };
-            self.update_clock_instant(clock_id as usize, secs, instant.nanos() as u64);
+            let instant = clock_instant(clock_id, instant);
+            self.update_clock_instant(clock_id as usize, instant.secs(), instant.nanos() as u64);
         }
     }
 }
 
+/// Converts an instant of the monotonic time to the instant of the given clock.
+fn clock_instant(clock_id: ClockId, instant: Instant) -> Instant {
+    let start_secs = *START_SECS_COUNT.get().unwrap();
+
+    match clock_id {
+        ClockId::CLOCK_REALTIME | ClockId::CLOCK_REALTIME_COARSE => {
+            Instant::new(instant.secs() + start_secs, instant.nanos()) + sleep_time()
+        }
+        ClockId::CLOCK_TAI => clock_instant(ClockId::CLOCK_REALTIME, instant) + ntp::tai_offset(),
+        ClockId::CLOCK_BOOTTIME => instant + sleep_time(),
+        _ => instant,
+    }
+}
+
 macro_rules! vdso_data_field_offset {
     ($field:ident) => {
         VDSO_VMO_LAYOUT.data_offset + core::mem::offset_of!(VdsoData, $field)
@@ -272,10 +283,7 @@ impl Vdso {
 
         data.update_high_res_instant(instant, instant_cycles);
 
-        // Update begins.
-        self.data_frame
-            .write_once(vdso_data_field_offset!(seq), &1)
-            .unwrap();
+        self.begin_update(&mut data);
 
         self.data_frame
             .write_val(vdso_data_field_offset!(last_cycles), &instant_cycles)
@@ -284,12 +292,7 @@ impl Vdso {
             self.update_data_frame_instant(clock_id, &mut data);
         }
 
-        // Update finishes.
-        // FIXME: To synchronize with the vDSO library, this needs to be an atomic write with the
-        // Release memory order.
-        self.data_frame
-            .write_once(vdso_data_field_offset!(seq), &0)
-            .unwrap();
+        self.end_update(&mut data);
     }
 
     fn update_coarse_res_instant(&self, instant: Instant) {
@@ -297,20 +300,34 @@ impl Vdso {
 
         data.update_coarse_res_instant(instant);
 
-        // Update begins.
-        self.data_frame
-            .write_once(vdso_data_field_offset!(seq), &1)
-            .unwrap();
+        self.begin_update(&mut data);
 
         for clock_id in COARSE_RES_CLOCK_IDS {
             self.update_data_frame_instant(clock_id, &mut data);
         }
 
-        // Update finishes.
+        self.end_update(&mut data);
+    }
+
+    /// Marks the beginning of an update of the vDSO data in the frame.
+    ///
+    /// Like the sequence counter in Linux, the sequence number is odd during an update. The vDSO
+    /// library retries reading the data if the sequence number is odd or has changed during the
+    /// read.
+    fn begin_update(&self, data: &mut VdsoData) {
+        data.seq = data.seq.wrapping_add(1);
+        self.data_frame
+            .write_once(vdso_data_field_offset!(seq), &data.seq)
+            .unwrap();
+    }
+
+    /// Marks the end of an update of the vDSO data in the frame.
+    fn end_update(&self, data: &mut VdsoData) {
+        data.seq = data.seq.wrapping_add(1);
         // FIXME: To synchronize with the vDSO library, this needs to be an atomic write with the
         // Release memory order.
         self.data_frame
-            .write_once(vdso_data_field_offset!(seq), &0)
+            .write_once(vdso_data_field_offset!(seq), &data.seq)
             .unwrap();
     }
 
@@ -345,6 +362,19 @@ fn update_vdso_coarse_res_instant(_guard: TimerGuard) {
     VDSO.get().unwrap().update_coarse_res_instant(instant);
 }
 
+/// Updates the instants in vDSO data after the offsets between clocks are changed.
+///
+/// Otherwise, the vDSO data would not reflect the new offsets until the next periodic update.
+pub(crate) fn update_vdso_clock_offsets() {
+    let Some(vdso) = VDSO.get() else {
+        return;
+    };
+
+    let (last_instant, last_cycles) = aster_time::default_clocksource().last_record();
+    vdso.update_high_res_instant(last_instant, last_cycles);
+    vdso.update_coarse_res_instant(Instant::from(read_monotonic_time()));
+}
+
 /// Initializes the time duration from 1970-01-01 00:00:00 to the start time.
 fn init_start_secs_count() {
     let time_duration = START_TIME
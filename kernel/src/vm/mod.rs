@@ -16,6 +16,10 @@ pub mod perms;
 pub mod vmar;
 pub mod vmo;
 
+pub(super) fn init() {
+    vmo::init();
+}
+
 #[ostd::global_frame_allocator]
 static FRAME_ALLOCATOR: FrameAllocator = FrameAllocator;
 
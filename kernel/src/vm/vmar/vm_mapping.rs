@@ -70,8 +70,8 @@ pub struct VmMapping {
     mapped_mem: MappedMemory,
     /// The path of the file that backs the mapping.
     ///
-    /// If the mapping is VMO-backed, the `mapped_mem` field should be the
-    /// page cache of the inode in the path.
+    /// If the mapping is VMO-backed and the inode in the path has a page
+    /// cache, the `mapped_mem` field should be the page cache.
     path: Option<Path>,
     /// Whether the mapping is shared.
     ///
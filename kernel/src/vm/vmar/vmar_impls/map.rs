@@ -156,8 +156,8 @@ impl<'a> VmarMapOptions<'a> {
 
     /// Sets the [`Path`] of the mapping.
     ///
-    /// If a [`Vmo`] is specified, the inode behind the [`Path`] must have
-    /// the [`Vmo`] as the page cache.
+    /// If a [`Vmo`] is specified and the inode behind the [`Path`] has a
+    /// page cache, the page cache must be the [`Vmo`].
     ///
     /// The [`Path`] of a mapping will be implicitly set if [`Self::mappable`]
     /// is set.
@@ -325,8 +325,10 @@ impl<'a> VmarMapOptions<'a> {
         // Parse the `Mappable` and prepare the `MappedMemory`.
         let (mapped_mem, io_mem) = match mappable {
             Some(Mappable::Vmo(vmo)) => {
-                if let Some(ref path) = path {
-                    debug_assert!(Arc::ptr_eq(&vmo, &path.inode().page_cache().unwrap()));
+                if let Some(ref path) = path
+                    && let Some(page_cache) = path.inode().page_cache()
+                {
+                    debug_assert!(Arc::ptr_eq(&vmo, &page_cache));
                 }
 
                 let is_writable_tracked = if let Some(ref path) = path
@@ -53,6 +53,11 @@ impl PageFaultInfo {
         }
     }
 
+    /// Returns the virtual address where the page fault occurred.
+    pub fn address(&self) -> Vaddr {
+        self.address
+    }
+
     /// Returns whether this page fault is forced.
     pub(in crate::vm::vmar) fn is_forced(&self) -> bool {
         self.is_forced
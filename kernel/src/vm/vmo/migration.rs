@@ -0,0 +1,52 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Migration of the anonymous pages of VMOs.
+//!
+//! The anonymous pages are allocated as movable pages, so they can be migrated
+//! to other pages during memory compaction.
+
+use ostd::{
+    impl_untyped_frame_meta_for,
+    mm::{
+        Frame,
+        frame::{
+            compaction::{FrameMigrator, register_frame_migrator},
+            meta::AnyFrameMeta,
+        },
+    },
+};
+
+use super::Vmo;
+use crate::prelude::*;
+
+/// The metadata of an anonymous page of a VMO.
+///
+/// It records the owner of the page so that the page can be migrated.
+#[derive(Debug)]
+pub(super) struct AnonPageMeta {
+    pub(super) vmo: Weak<Vmo>,
+    pub(super) page_idx: usize,
+}
+
+impl_untyped_frame_meta_for!(AnonPageMeta);
+
+/// The migrator of the anonymous pages of VMOs.
+struct AnonPageMigrator;
+
+impl FrameMigrator for AnonPageMigrator {
+    fn migrate(&self, frame: Frame<dyn AnyFrameMeta>) -> bool {
+        let Ok(page) = Frame::<AnonPageMeta>::try_from(frame) else {
+            return false;
+        };
+        let Some(vmo) = page.meta().vmo.upgrade() else {
+            // The VMO is being dropped. So is the page.
+            return false;
+        };
+
+        vmo.migrate_page(&page)
+    }
+}
+
+pub(super) fn init() {
+    register_frame_migrator::<AnonPageMeta>(&AnonPageMigrator);
+}
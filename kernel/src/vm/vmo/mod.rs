@@ -13,20 +13,27 @@ use core::{
 use align_ext::AlignExt;
 use ostd::{
     mm::{
-        FrameAllocOptions, UFrame, VmIo, VmIoFill, VmReader, VmWriter, io::util::HasVmReaderWriter,
+        Frame, FrameAllocOptions, HasPaddr, UFrame, VmIo, VmIoFill, VmReader, VmWriter,
+        frame::compaction::migrate_frame, io::util::HasVmReaderWriter,
     },
     task::disable_preempt,
 };
 use xarray::{Cursor, LockedXArray, XArray};
 
+use self::migration::AnonPageMeta;
 use crate::prelude::*;
 
+mod migration;
 mod options;
 mod pager;
 
 pub use options::VmoOptions;
 pub use pager::Pager;
 
+pub(super) fn init() {
+    migration::init();
+}
+
 /// Virtual Memory Objects (VMOs) are a type of capability that represents a
 /// range of memory pages.
 ///
@@ -61,6 +68,11 @@ pub use pager::Pager;
 /// `Vmo` is easier to use (by offering more powerful APIs) and
 /// harder to misuse (thanks to its nature of being capability).
 pub struct Vmo {
+    /// A weak reference to the VMO itself.
+    ///
+    /// It is recorded in the metadata of the anonymous pages, so that the
+    /// pages can be migrated.
+    this: Weak<Vmo>,
     pager: Option<Arc<dyn Pager>>,
     /// Flags
     flags: VmoFlags,
@@ -151,7 +163,19 @@ impl Vmo {
     /// This operation may involve I/O operations if the VMO is backed by a pager.
     fn prepare_page(&self, page_idx: usize, commit_flags: CommitFlags) -> Result<UFrame> {
         match &self.pager {
-            None => Ok(FrameAllocOptions::new().alloc_frame()?.into()),
+            None if self.flags.contains(VmoFlags::DMA) => {
+                Ok(FrameAllocOptions::new().alloc_frame()?.into())
+            }
+            None => {
+                let meta = AnonPageMeta {
+                    vmo: self.this.clone(),
+                    page_idx,
+                };
+                let page = FrameAllocOptions::new()
+                    .movable(true)
+                    .alloc_frame_with(meta)?;
+                Ok(page.into())
+            }
             Some(pager) => {
                 if commit_flags.will_overwrite() {
                     pager.commit_overwrite(page_idx)
@@ -187,8 +211,20 @@ impl Vmo {
         &self,
         cursor: &mut Cursor<'_, UFrame>,
     ) -> core::result::Result<UFrame, VmoCommitError> {
-        if let Some(committed_page) = cursor.load() {
-            return Ok(committed_page.clone());
+        while let Some(committed_page) = cursor.load() {
+            // The page is being migrated if it cannot be cloned. Then the new
+            // page will be stored soon.
+            let Some(page) = committed_page.try_clone() else {
+                core::hint::spin_loop();
+                continue;
+            };
+            // The page may have been migrated before being cloned.
+            if cursor
+                .load()
+                .is_some_and(|current| current.paddr() == page.paddr())
+            {
+                return Ok(page);
+            }
         }
 
         if let Some(pager) = &self.pager {
@@ -461,6 +497,32 @@ impl Vmo {
         Ok(())
     }
 
+    /// Migrates an anonymous page of the VMO to a new page.
+    ///
+    /// It returns whether the page is migrated.
+    fn migrate_page(&self, page: &Frame<AnonPageMeta>) -> bool {
+        let page_idx = page.meta().page_idx;
+
+        let mut locked_pages = self.pages.lock();
+        let mut cursor = locked_pages.cursor_mut(page_idx as u64);
+        if cursor
+            .load()
+            .is_none_or(|committed_page| committed_page.paddr() != page.paddr())
+        {
+            return false;
+        }
+
+        // The only references are the committed one and `page`. If the page is
+        // mapped, there are also references from the page tables. Such pages
+        // are not migrated since we cannot find the page table entries to
+        // update without a reverse mapping.
+        let meta = AnonPageMeta {
+            vmo: self.this.clone(),
+            page_idx,
+        };
+        migrate_frame(page, 2, meta, |new_page| cursor.store(new_page.into()))
+    }
+
     /// Returns the status of writable mappings of the VMO.
     pub fn writable_mapping_status(&self) -> &WritableMappingStatus {
         // Only writable file-backed mappings may need to be tracked.
@@ -79,22 +79,22 @@ impl VmoOptions {
         let VmoOptions {
             size, flags, pager, ..
         } = self;
-        let vmo = alloc_vmo(size, flags, pager)?;
-        Ok(Arc::new(vmo))
+        alloc_vmo(size, flags, pager)
     }
 }
 
-fn alloc_vmo(size: usize, flags: VmoFlags, pager: Option<Arc<dyn Pager>>) -> Result<Vmo> {
+fn alloc_vmo(size: usize, flags: VmoFlags, pager: Option<Arc<dyn Pager>>) -> Result<Arc<Vmo>> {
     let size = size.align_up(PAGE_SIZE);
     let pages = committed_pages_if_continuous(flags, size)?;
     let writable_mapping_status = WritableMappingStatus::default();
-    Ok(Vmo {
+    Ok(Arc::new_cyclic(|this| Vmo {
+        this: this.clone(),
         pager,
         flags,
         pages,
         size: AtomicUsize::new(size),
         writable_mapping_status,
-    })
+    }))
 }
 
 fn committed_pages_if_continuous(flags: VmoFlags, size: usize) -> Result<XArray<UFrame>> {
@@ -119,9 +119,13 @@ fn committed_pages_if_continuous(flags: VmoFlags, size: usize) -> Result<XArray<
 
 #[cfg(ktest)]
 mod test {
-    use ostd::{mm::VmIo, prelude::*};
+    use ostd::{
+        mm::{Frame, HasPaddr, VmIo, frame::meta::AnyFrameMeta},
+        prelude::*,
+    };
 
     use super::*;
+    use crate::vm::vmo::{CommitFlags, migration::AnonPageMeta};
 
     #[ktest]
     fn alloc_vmo() {
@@ -168,4 +172,26 @@ mod test {
         vmo.resize(PAGE_SIZE).unwrap();
         assert_eq!(vmo.read_val::<u8>(10).unwrap(), 42);
     }
+
+    #[ktest]
+    fn migrate_anon_page() {
+        let vmo = VmoOptions::new(PAGE_SIZE).alloc().unwrap();
+        vmo.write_val(10, &42u8).unwrap();
+
+        let page = vmo.commit_on(0, CommitFlags::empty()).unwrap();
+        let paddr = page.paddr();
+        let page = Frame::<dyn AnyFrameMeta>::from_unsized(page);
+        let page = Frame::<AnonPageMeta>::try_from(page).unwrap();
+
+        // A page with extra references should not be migrated.
+        let shared = page.clone();
+        assert!(!vmo.migrate_page(&page));
+        drop(shared);
+
+        assert!(vmo.migrate_page(&page));
+        drop(page);
+        let page = vmo.commit_on(0, CommitFlags::empty()).unwrap();
+        assert_ne!(page.paddr(), paddr);
+        assert_eq!(vmo.read_val::<u8>(10).unwrap(), 42);
+    }
 }
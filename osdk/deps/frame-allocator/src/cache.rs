@@ -1,6 +1,11 @@
 // SPDX-License-Identifier: MPL-2.0
 
-//! A fixed-size local cache for frame allocation.
+//! Fixed-size local caches for frame allocation.
+//!
+//! Each CPU caches free segments of a few small sizes, so that the frequent
+//! small allocations (e.g., for page faults) neither lock nor walk the buddy
+//! system. The caches are refilled from and drained to the pools in batches
+//! according to their watermarks.
 
 use core::{alloc::Layout, cell::RefCell};
 
@@ -14,26 +19,37 @@ cpu_local! {
     static CACHE: RefCell<CacheOfSizes> = RefCell::new(CacheOfSizes::new());
 }
 
+/// The maximum number of contiguous frames of the cached segments.
+const MAX_CACHED_FRAMES: usize = 4;
+
 struct CacheOfSizes {
-    cache1: CacheArray<1, 12>,
-    cache2: CacheArray<2, 6>,
-    cache3: CacheArray<3, 6>,
-    cache4: CacheArray<4, 6>,
+    cache1: CacheArray<1, 64>,
+    cache2: CacheArray<2, 16>,
+    cache3: CacheArray<3, 8>,
+    cache4: CacheArray<4, 8>,
 }
 
 /// A fixed-size local cache for frame allocation.
 ///
-/// Each cache array contains at most `COUNT` segments. Each segment contains
-/// `NR_CONT_FRAMES` contiguous frames.
-struct CacheArray<const NR_CONT_FRAMES: usize, const COUNT: usize> {
-    inner: [Option<Paddr>; COUNT],
+/// Each cache array contains at most `HIGH` segments, which is its high
+/// watermark. Each segment contains `NR_CONT_FRAMES` contiguous frames.
+///
+/// If the cache is empty when allocating, [`Self::BATCH`] segments are
+/// allocated from the pools at once. If the cache reaches the high watermark
+/// when deallocating, [`Self::BATCH`] segments are deallocated to the pools
+/// at once.
+struct CacheArray<const NR_CONT_FRAMES: usize, const HIGH: usize> {
+    inner: [Option<Paddr>; HIGH],
     size: usize,
 }
 
-impl<const NR_CONT_FRAMES: usize, const COUNT: usize> CacheArray<NR_CONT_FRAMES, COUNT> {
+impl<const NR_CONT_FRAMES: usize, const HIGH: usize> CacheArray<NR_CONT_FRAMES, HIGH> {
+    /// The number of segments to refill or drain at once.
+    const BATCH: usize = HIGH / 4;
+
     const fn new() -> Self {
         Self {
-            inner: [const { None }; COUNT],
+            inner: [const { None }; HIGH],
             size: 0,
         }
     }
@@ -46,19 +62,21 @@ impl<const NR_CONT_FRAMES: usize, const COUNT: usize> CacheArray<NR_CONT_FRAMES,
     /// Allocates a segment of frames.
     ///
     /// It may allocate directly from this cache. If the cache is empty, it
-    /// will fill the cache.
+    /// will refill the cache.
     fn alloc(&mut self, guard: &DisabledLocalIrqGuard) -> Option<Paddr> {
         if let Some(frame) = self.pop_front() {
             return Some(frame);
         }
 
-        let nr_to_alloc = COUNT * 2 / 3;
-        let allocated = super::pools::alloc(
-            guard,
-            Layout::from_size_align(nr_to_alloc * Self::segment_size(), PAGE_SIZE).unwrap(),
-        )?;
+        let batch_layout =
+            Layout::from_size_align(Self::BATCH * Self::segment_size(), PAGE_SIZE).unwrap();
+        let Some(allocated) = super::pools::alloc(guard, batch_layout) else {
+            // The memory may be too fragmented for a batch.
+            let layout = Layout::from_size_align(Self::segment_size(), PAGE_SIZE).unwrap();
+            return super::pools::alloc(guard, layout);
+        };
 
-        for i in 1..nr_to_alloc {
+        for i in 1..Self::BATCH {
             self.push_front(allocated + i * Self::segment_size());
         }
 
@@ -71,9 +89,7 @@ impl<const NR_CONT_FRAMES: usize, const COUNT: usize> CacheArray<NR_CONT_FRAMES,
     /// deallocate to the global pool.
     fn dealloc(&mut self, guard: &DisabledLocalIrqGuard, addr: Paddr) {
         if self.push_front(addr).is_none() {
-            let nr_to_dealloc = COUNT * 2 / 3 + 1;
-
-            let segments = (0..nr_to_dealloc).map(|i| {
+            let segments = (0..Self::BATCH).map(|i| {
                 if i == 0 {
                     (addr, Self::segment_size())
                 } else {
@@ -85,8 +101,15 @@ impl<const NR_CONT_FRAMES: usize, const COUNT: usize> CacheArray<NR_CONT_FRAMES,
         };
     }
 
+    /// Deallocates all the cached segments to the pools.
+    fn drain(&mut self, guard: &DisabledLocalIrqGuard) {
+        let segments =
+            core::iter::from_fn(|| self.pop_front()).map(|addr| (addr, Self::segment_size()));
+        super::pools::dealloc(guard, segments);
+    }
+
     fn push_front(&mut self, frame: Paddr) -> Option<()> {
-        if self.size == COUNT {
+        if self.size == HIGH {
             return None;
         }
 
@@ -115,41 +138,106 @@ impl CacheOfSizes {
             cache4: CacheArray::new(),
         }
     }
+
+    fn alloc(&mut self, guard: &DisabledLocalIrqGuard, nr_frames: usize) -> Option<Paddr> {
+        match nr_frames {
+            1 => self.cache1.alloc(guard),
+            2 => self.cache2.alloc(guard),
+            3 => self.cache3.alloc(guard),
+            4 => self.cache4.alloc(guard),
+            _ => unreachable!("{} frames are not cached", nr_frames),
+        }
+    }
+
+    fn dealloc(&mut self, guard: &DisabledLocalIrqGuard, addr: Paddr, nr_frames: usize) {
+        match nr_frames {
+            1 => self.cache1.dealloc(guard, addr),
+            2 => self.cache2.dealloc(guard, addr),
+            3 => self.cache3.dealloc(guard, addr),
+            4 => self.cache4.dealloc(guard, addr),
+            _ => unreachable!("{} frames are not cached", nr_frames),
+        }
+    }
+
+    fn drain(&mut self, guard: &DisabledLocalIrqGuard) {
+        self.cache1.drain(guard);
+        self.cache2.drain(guard);
+        self.cache3.drain(guard);
+        self.cache4.drain(guard);
+    }
+}
+
+/// Returns whether allocations of the layout are served by the caches.
+fn is_cached(layout: Layout) -> bool {
+    layout.align() <= layout.size() && layout.size() / PAGE_SIZE <= MAX_CACHED_FRAMES
 }
 
 pub(super) fn alloc(guard: &DisabledLocalIrqGuard, layout: Layout) -> Option<Paddr> {
-    let nr_frames = layout.size() / PAGE_SIZE;
-    if layout.align() > layout.size() {
+    if !is_cached(layout) {
         return super::pools::alloc(guard, layout);
     }
 
     let cache_cell = CACHE.get_with(guard);
     let mut cache = cache_cell.borrow_mut();
 
-    match nr_frames {
-        1 => cache.cache1.alloc(guard),
-        2 => cache.cache2.alloc(guard),
-        3 => cache.cache3.alloc(guard),
-        4 => cache.cache4.alloc(guard),
-        _ => super::pools::alloc(guard, layout),
+    cache.alloc(guard, layout.size() / PAGE_SIZE)
+}
+
+/// Allocates multiple segments of the layout.
+///
+/// It returns the number of allocated segments, whose addresses are written
+/// to the beginning of `addrs`.
+pub(super) fn alloc_bulk(
+    guard: &DisabledLocalIrqGuard,
+    layout: Layout,
+    addrs: &mut [Paddr],
+) -> usize {
+    if !is_cached(layout) {
+        for (i, addr) in addrs.iter_mut().enumerate() {
+            let Some(allocated) = super::pools::alloc(guard, layout) else {
+                return i;
+            };
+            *addr = allocated;
+        }
+        return addrs.len();
     }
+
+    let cache_cell = CACHE.get_with(guard);
+    let mut cache = cache_cell.borrow_mut();
+
+    for (i, addr) in addrs.iter_mut().enumerate() {
+        let Some(allocated) = cache.alloc(guard, layout.size() / PAGE_SIZE) else {
+            return i;
+        };
+        *addr = allocated;
+    }
+    addrs.len()
 }
 
 pub(super) fn dealloc(guard: &DisabledLocalIrqGuard, addr: Paddr, size: usize) {
+    dealloc_bulk(guard, &[addr], size);
+}
+
+/// Deallocates multiple segments of the same size.
+pub(super) fn dealloc_bulk(guard: &DisabledLocalIrqGuard, addrs: &[Paddr], size: usize) {
     let nr_frames = size / PAGE_SIZE;
-    if nr_frames > 4 {
-        super::pools::dealloc(guard, [(addr, size)].into_iter());
+    if nr_frames > MAX_CACHED_FRAMES {
+        super::pools::dealloc(guard, addrs.iter().map(|addr| (*addr, size)));
         return;
     }
 
     let cache_cell = CACHE.get_with(guard);
     let mut cache = cache_cell.borrow_mut();
 
-    match nr_frames {
-        1 => cache.cache1.dealloc(guard, addr),
-        2 => cache.cache2.dealloc(guard, addr),
-        3 => cache.cache3.dealloc(guard, addr),
-        4 => cache.cache4.dealloc(guard, addr),
-        _ => super::pools::dealloc(guard, [(addr, size)].into_iter()),
+    for addr in addrs {
+        cache.dealloc(guard, *addr, nr_frames);
     }
 }
+
+/// Deallocates all the segments in the local caches to the pools.
+pub(super) fn drain(guard: &DisabledLocalIrqGuard) {
+    let cache_cell = CACHE.get_with(guard);
+    let mut cache = cache_cell.borrow_mut();
+
+    cache.drain(guard);
+}
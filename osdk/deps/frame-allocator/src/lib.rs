@@ -38,6 +38,7 @@ use ostd::{
 
 mod cache;
 mod chunk;
+mod movable;
 mod pools;
 mod set;
 mod smp_counter;
@@ -65,7 +66,19 @@ pub struct FrameAllocator;
 impl GlobalFrameAllocator for FrameAllocator {
     fn alloc(&self, layout: Layout) -> Option<Paddr> {
         let guard = irq::disable_local();
-        let res = cache::alloc(&guard, layout);
+        let res = cache::alloc(&guard, layout).or_else(|| {
+            // The free memory may be held by the local caches.
+            cache::drain(&guard);
+            movable::drain(&guard);
+            cache::alloc(&guard, layout).or_else(|| {
+                // Fall back to the free frames in the movable page blocks.
+                if movable::is_supported(layout) {
+                    movable::alloc(&guard)
+                } else {
+                    None
+                }
+            })
+        });
         if res.is_some() {
             TOTAL_FREE_SIZE.sub(guard.current_cpu(), layout.size());
         }
@@ -75,7 +88,53 @@ impl GlobalFrameAllocator for FrameAllocator {
     fn dealloc(&self, addr: Paddr, size: usize) {
         let guard = irq::disable_local();
         TOTAL_FREE_SIZE.add(guard.current_cpu(), size);
-        cache::dealloc(&guard, addr, size);
+        if movable::is_movable(addr) {
+            movable::dealloc(&guard, addr, size);
+        } else {
+            cache::dealloc(&guard, addr, size);
+        }
+    }
+
+    fn alloc_movable(&self, layout: Layout) -> Option<Paddr> {
+        if !movable::is_supported(layout) {
+            return self.alloc(layout);
+        }
+
+        let guard = irq::disable_local();
+        let Some(res) = movable::alloc(&guard) else {
+            // There are no free page blocks for movable frames.
+            return self.alloc(layout);
+        };
+        TOTAL_FREE_SIZE.sub(guard.current_cpu(), layout.size());
+        Some(res)
+    }
+
+    fn alloc_bulk(&self, layout: Layout, addrs: &mut [Paddr]) -> usize {
+        let guard = irq::disable_local();
+        let mut nr_allocated = cache::alloc_bulk(&guard, layout, addrs);
+        if nr_allocated < addrs.len() {
+            // The free memory may be held by the local caches.
+            cache::drain(&guard);
+            nr_allocated += cache::alloc_bulk(&guard, layout, &mut addrs[nr_allocated..]);
+        }
+        TOTAL_FREE_SIZE.sub(guard.current_cpu(), nr_allocated * layout.size());
+        nr_allocated
+    }
+
+    fn dealloc_bulk(&self, addrs: &[Paddr], size: usize) {
+        let guard = irq::disable_local();
+        TOTAL_FREE_SIZE.add(guard.current_cpu(), addrs.len() * size);
+        if addrs.iter().any(|addr| movable::is_movable(*addr)) {
+            for &addr in addrs {
+                if movable::is_movable(addr) {
+                    movable::dealloc(&guard, addr, size);
+                } else {
+                    cache::dealloc(&guard, addr, size);
+                }
+            }
+        } else {
+            cache::dealloc_bulk(&guard, addrs, size);
+        }
     }
 
     fn add_free_memory(&self, addr: Paddr, size: usize) {
@@ -83,4 +142,9 @@ impl GlobalFrameAllocator for FrameAllocator {
         TOTAL_FREE_SIZE.add(guard.current_cpu(), size);
         pools::add_free_memory(&guard, addr, size);
     }
+
+    fn is_free(&self, addr: Paddr) -> bool {
+        let guard = irq::disable_local();
+        movable::is_free(addr) || pools::is_free(&guard, addr)
+    }
 }
@@ -0,0 +1,207 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Grouping movable frames in page blocks.
+//!
+//! Movable frames are allocated from whole page blocks of [`PAGEBLOCK_SIZE`]
+//! that are taken from the pools, so they are not mixed with unmovable
+//! frames. Memory compaction can then free a whole page block by migrating
+//! the movable frames in it (see [`ostd::mm::frame::compaction`]). Once all
+//! the frames in a movable page block are freed, the page block is returned
+//! to the global pool for allocations of any kind.
+//!
+//! Each CPU caches a few free movable frames. They may keep a few page blocks
+//! from being freed until the caches are drained.
+
+use core::{
+    alloc::Layout,
+    cell::RefCell,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use ostd::{
+    cpu_local,
+    irq::DisabledLocalIrqGuard,
+    mm::{PAGE_SIZE, Paddr, frame::compaction::PAGEBLOCK_SIZE},
+    sync::{LocalIrqDisabled, SpinLock},
+};
+
+use crate::{
+    chunk::{BuddyOrder, split_to_chunks},
+    set::BuddySet,
+};
+
+/// The order of a page block.
+const PAGEBLOCK_ORDER: BuddyOrder = (PAGEBLOCK_SIZE / PAGE_SIZE).trailing_zeros() as BuddyOrder;
+
+/// The free frames in the movable page blocks.
+///
+/// A page block is taken out of the set once it is coalesced as a whole, so
+/// the set never needs to hold chunks larger than a page block.
+static MOVABLE_POOL: SpinLock<MovablePool, LocalIrqDisabled> = SpinLock::new(BuddySet::new_empty());
+
+type MovablePool = BuddySet<{ PAGEBLOCK_ORDER + 1 }>;
+
+/// The maximum physical address of the movable page blocks.
+///
+/// Page blocks above it are never used for movable frames. A maximum physical
+/// address of 256 GiB needs 16 KiB of bitmap.
+const MAX_MOVABLE_PADDR: Paddr = 256 << 30;
+
+const NR_BITMAP_WORDS: usize = MAX_MOVABLE_PADDR / PAGEBLOCK_SIZE / u64::BITS as usize;
+
+/// The bitmap of the movable page blocks.
+static MOVABLE_BLOCKS: [AtomicU64; NR_BITMAP_WORDS] =
+    [const { AtomicU64::new(0) }; NR_BITMAP_WORDS];
+
+cpu_local! {
+    static CACHE: RefCell<MovableCache> = RefCell::new(MovableCache::new());
+}
+
+/// Returns whether allocations of the layout can be movable.
+pub(super) fn is_supported(layout: Layout) -> bool {
+    layout.size() == PAGE_SIZE && layout.align() <= PAGE_SIZE
+}
+
+/// Returns whether the frame is in a movable page block.
+pub(super) fn is_movable(addr: Paddr) -> bool {
+    block_bit(addr).is_some_and(|(word, bit)| word.load(Ordering::Relaxed) & bit != 0)
+}
+
+/// Tells if a frame is the head of a free chunk in the movable page blocks.
+pub(super) fn is_free(addr: Paddr) -> bool {
+    is_movable(addr) && MOVABLE_POOL.lock().contains(addr)
+}
+
+/// Allocates a movable frame.
+///
+/// It returns `None` if there are neither free movable frames nor free page
+/// blocks.
+pub(super) fn alloc(guard: &DisabledLocalIrqGuard) -> Option<Paddr> {
+    let cache_cell = CACHE.get_with(guard);
+    let mut cache = cache_cell.borrow_mut();
+
+    cache.alloc(guard)
+}
+
+/// Deallocates a range of frames in a movable page block.
+pub(super) fn dealloc(guard: &DisabledLocalIrqGuard, addr: Paddr, size: usize) {
+    if size != PAGE_SIZE {
+        dealloc_to_pool(guard, [(addr, size)].into_iter());
+        return;
+    }
+
+    let cache_cell = CACHE.get_with(guard);
+    let mut cache = cache_cell.borrow_mut();
+
+    cache.dealloc(guard, addr);
+}
+
+/// Deallocates all the movable frames in the local cache to the pool.
+pub(super) fn drain(guard: &DisabledLocalIrqGuard) {
+    let cache_cell = CACHE.get_with(guard);
+    let mut cache = cache_cell.borrow_mut();
+
+    cache.drain(guard);
+}
+
+/// A local cache of free movable frames.
+struct MovableCache {
+    frames: [Paddr; Self::HIGH],
+    size: usize,
+}
+
+impl MovableCache {
+    /// The maximum number of frames in the cache.
+    const HIGH: usize = 32;
+    /// The number of frames to refill or drain at once.
+    const BATCH: usize = Self::HIGH / 4;
+
+    const fn new() -> Self {
+        Self {
+            frames: [0; Self::HIGH],
+            size: 0,
+        }
+    }
+
+    fn alloc(&mut self, guard: &DisabledLocalIrqGuard) -> Option<Paddr> {
+        if self.size == 0 {
+            let mut pool = MOVABLE_POOL.lock();
+            while self.size < Self::BATCH {
+                let Some(addr) = alloc_from_pool(guard, &mut pool) else {
+                    break;
+                };
+                self.frames[self.size] = addr;
+                self.size += 1;
+            }
+        }
+
+        if self.size == 0 {
+            return None;
+        }
+        self.size -= 1;
+        Some(self.frames[self.size])
+    }
+
+    fn dealloc(&mut self, guard: &DisabledLocalIrqGuard, addr: Paddr) {
+        if self.size == Self::HIGH {
+            self.size -= Self::BATCH;
+            let drained = &self.frames[self.size..self.size + Self::BATCH];
+            dealloc_to_pool(guard, drained.iter().map(|addr| (*addr, PAGE_SIZE)));
+        }
+
+        self.frames[self.size] = addr;
+        self.size += 1;
+    }
+
+    fn drain(&mut self, guard: &DisabledLocalIrqGuard) {
+        let drained = &self.frames[..self.size];
+        dealloc_to_pool(guard, drained.iter().map(|addr| (*addr, PAGE_SIZE)));
+        self.size = 0;
+    }
+}
+
+/// Allocates a frame from the pool, taking a free page block from the pools
+/// if there are no free movable frames.
+fn alloc_from_pool(guard: &DisabledLocalIrqGuard, pool: &mut MovablePool) -> Option<Paddr> {
+    if let Some(addr) = pool.alloc_chunk(0) {
+        return Some(addr);
+    }
+
+    let block_layout = Layout::from_size_align(PAGEBLOCK_SIZE, PAGEBLOCK_SIZE).unwrap();
+    let block = super::pools::alloc(guard, block_layout)?;
+    let Some((word, bit)) = block_bit(block) else {
+        super::pools::dealloc(guard, [(block, PAGEBLOCK_SIZE)].into_iter());
+        return None;
+    };
+    word.fetch_or(bit, Ordering::Relaxed);
+    pool.insert_chunk(block, PAGEBLOCK_ORDER);
+
+    pool.alloc_chunk(0)
+}
+
+/// Deallocates movable frames to the pool, returning the page blocks that
+/// become free to the global pool.
+fn dealloc_to_pool(guard: &DisabledLocalIrqGuard, segments: impl Iterator<Item = (Paddr, usize)>) {
+    let mut pool = MOVABLE_POOL.lock();
+
+    segments.for_each(|(addr, size)| {
+        split_to_chunks(addr, size).for_each(|(addr, order)| {
+            pool.insert_chunk(addr, order);
+        });
+    });
+
+    while let Some(block) = pool.alloc_chunk(PAGEBLOCK_ORDER) {
+        let (word, bit) = block_bit(block).unwrap();
+        word.fetch_and(!bit, Ordering::Relaxed);
+        // Return the page block directly to the global pool, where it can be
+        // coalesced for high-order allocations on any CPU.
+        super::pools::add_free_memory(guard, block, PAGEBLOCK_SIZE);
+    }
+}
+
+/// Returns the word and the bit of the page block in the bitmap.
+fn block_bit(addr: Paddr) -> Option<(&'static AtomicU64, u64)> {
+    let block_idx = addr / PAGEBLOCK_SIZE;
+    let word = MOVABLE_BLOCKS.get(block_idx / u64::BITS as usize)?;
+    Some((word, 1 << (block_idx % u64::BITS as usize)))
+}
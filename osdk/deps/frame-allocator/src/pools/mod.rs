@@ -37,7 +37,7 @@ cpu_local! {
 /// maximum size of each allocation.
 ///
 /// A maximum buddy order of 32 supports up to 4KiB*2^31 = 8 TiB of chunks.
-const MAX_BUDDY_ORDER: BuddyOrder = 32;
+pub(crate) const MAX_BUDDY_ORDER: BuddyOrder = 32;
 
 /// Maximum supported order of the buddy system for CPU-local buddy system.
 ///
@@ -113,6 +113,17 @@ pub(super) fn add_free_memory(_guard: &DisabledLocalIrqGuard, addr: Paddr, size:
     });
 }
 
+/// Tells if a frame is the head of a free chunk in the pools.
+///
+/// The pools of other CPUs are not checked, so the frame may be free even if
+/// it returns `false`.
+pub(super) fn is_free(guard: &DisabledLocalIrqGuard, addr: Paddr) -> bool {
+    let local_pool_cell = LOCAL_POOL.get_with(guard);
+    let mut local_pool = local_pool_cell.borrow_mut();
+
+    local_pool.contains(addr) || GLOBAL_POOL.lock().contains(addr)
+}
+
 fn do_dealloc(
     local_pool: &mut BuddySet<MAX_LOCAL_BUDDY_ORDER>,
     global_pool: &mut OnDemandGlobalLock,
@@ -26,6 +26,11 @@ impl<const MAX_ORDER: BuddyOrder> BuddySet<MAX_ORDER> {
         self.total_size
     }
 
+    /// Tells if a frame is the head of a free chunk in the set.
+    pub(crate) fn contains(&mut self, addr: Paddr) -> bool {
+        self.lists.iter_mut().any(|list| list.contains(addr))
+    }
+
     /// Inserts a free chunk into the set.
     pub(crate) fn insert_chunk(&mut self, addr: Paddr, order: BuddyOrder) {
         debug_assert!(order < MAX_ORDER);
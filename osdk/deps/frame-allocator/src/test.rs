@@ -13,6 +13,10 @@ use ostd::{
 };
 
 use super::FrameAllocator;
+use crate::{
+    chunk::{greater_order_of, size_of_order},
+    pools::MAX_BUDDY_ORDER,
+};
 
 #[ktest]
 fn frame_allocator_alloc_layout_match() {
@@ -27,6 +31,69 @@ fn frame_allocator_alloc_layout_match() {
     assert_allocation_well_formed(Layout::from_size_align(PAGE_SIZE * 16, PAGE_SIZE * 16).unwrap());
 }
 
+#[ktest]
+fn frame_allocator_alloc_dealloc_bulk() {
+    let instance = FrameAllocator;
+    let layout = Layout::from_size_align(PAGE_SIZE, PAGE_SIZE).unwrap();
+
+    // Allocate more frames than a local cache can hold.
+    let mut allocated = [0; 200];
+    assert_eq!(instance.alloc_bulk(layout, &mut allocated), allocated.len());
+
+    for frame in allocated {
+        let frame = UniqueFrame::from_unused(frame, ())
+            .expect("Metadata not well-formed after bulk allocation");
+        frame.reset_as_unused();
+    }
+    let mut sorted = allocated;
+    sorted.sort_unstable();
+    assert!(sorted.windows(2).all(|pair| pair[0] != pair[1]));
+
+    instance.dealloc_bulk(&allocated, PAGE_SIZE);
+}
+
+#[ktest]
+fn frame_allocator_alloc_dealloc_movable() {
+    let instance = FrameAllocator;
+    let layout = Layout::from_size_align(PAGE_SIZE, PAGE_SIZE).unwrap();
+
+    // Allocate more frames than a local cache can hold.
+    let mut allocated = [0; 100];
+    for addr in allocated.iter_mut() {
+        *addr = instance.alloc_movable(layout).unwrap();
+        assert!(crate::movable::is_movable(*addr));
+
+        let frame = UniqueFrame::from_unused(*addr, ())
+            .expect("Metadata not well-formed after movable allocation");
+        frame.reset_as_unused();
+    }
+    let mut sorted = allocated;
+    sorted.sort_unstable();
+    assert!(sorted.windows(2).all(|pair| pair[0] != pair[1]));
+
+    instance.dealloc_bulk(&allocated, PAGE_SIZE);
+}
+
+#[ktest]
+fn frame_allocator_is_free() {
+    let instance = FrameAllocator;
+    // Allocate a chunk that bypasses the local caches, whose free frames are
+    // not uniquely owned.
+    let layout = Layout::from_size_align(PAGE_SIZE * 16, PAGE_SIZE * 16).unwrap();
+    let addr = instance.alloc(layout).unwrap();
+
+    // A frame that is uniquely owned by others is not free.
+    let frame = UniqueFrame::from_unused(addr, ()).unwrap();
+    assert!(!instance.is_free(addr));
+    frame.reset_as_unused();
+
+    instance.dealloc(addr, layout.size());
+    // The chunk may be coalesced with its buddies into a larger chunk.
+    let order = greater_order_of(layout.size());
+    let mut chunk_heads = (order..MAX_BUDDY_ORDER).map(|order| addr & !(size_of_order(order) - 1));
+    assert!(chunk_heads.any(|head| instance.is_free(head)));
+}
+
 #[track_caller]
 fn assert_allocation_well_formed(layout: Layout) {
     let instance = FrameAllocator;
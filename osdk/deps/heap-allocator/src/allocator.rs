@@ -2,6 +2,7 @@
 
 //! A global allocator implementation of many slab caches.
 
+use alloc::{vec, vec::Vec};
 use core::{
     alloc::{AllocError, Layout},
     cell::RefCell,
@@ -97,127 +98,325 @@ pub const fn type_from_layout(layout: Layout) -> Option<SlotInfo> {
     None
 }
 
-struct Heap {
-    slab8: SlabCache<8>,
-    slab16: SlabCache<16>,
-    slab32: SlabCache<32>,
-    slab64: SlabCache<64>,
-    slab128: SlabCache<128>,
-    slab256: SlabCache<256>,
-    slab512: SlabCache<512>,
-    slab1024: SlabCache<1024>,
-    slab2048: SlabCache<2048>,
+/// The statistics of the slab cache of a slot size class.
+///
+/// They provide the numbers reported in `/proc/slabinfo`.
+#[derive(Debug, Clone, Copy)]
+pub struct SlabCacheStats {
+    /// The size of the slots in bytes.
+    pub slot_size: usize,
+    /// The number of slots allocated from the slabs.
+    ///
+    /// The slots cached in the magazines are also counted.
+    pub nr_active_slots: usize,
+    /// The total number of slots in the slabs.
+    pub nr_slots: usize,
+    /// The number of slots in a slab.
+    pub slots_per_slab: usize,
+    /// The number of slabs that have allocated slots.
+    pub nr_active_slabs: usize,
+    /// The total number of slabs.
+    pub nr_slabs: usize,
+    /// The maximum number of slots in a magazine.
+    pub magazine_capacity: usize,
+    /// The number of slots in the magazines of the depot.
+    pub nr_depot_slots: usize,
 }
 
-impl Heap {
+/// The size in bytes of the slots that a magazine can hold.
+const MAGAZINE_SIZE: usize = PAGE_SIZE;
+/// The maximum number of full magazines in the depot of each slot size class.
+const DEPOT_CAPACITY: usize = 16;
+
+/// A magazine, i.e., a bounded stack of free slots.
+struct Magazine<const SLOT_SIZE: usize> {
+    list: SlabSlotList<SLOT_SIZE>,
+    nr_rounds: usize,
+}
+
+impl<const SLOT_SIZE: usize> Magazine<SLOT_SIZE> {
+    const CAPACITY: usize = MAGAZINE_SIZE / SLOT_SIZE;
+
     const fn new() -> Self {
         Self {
-            slab8: SlabCache::new(),
-            slab16: SlabCache::new(),
-            slab32: SlabCache::new(),
-            slab64: SlabCache::new(),
-            slab128: SlabCache::new(),
-            slab256: SlabCache::new(),
-            slab512: SlabCache::new(),
-            slab1024: SlabCache::new(),
-            slab2048: SlabCache::new(),
+            list: SlabSlotList::new(),
+            nr_rounds: 0,
         }
     }
 
-    fn alloc(&mut self, class: CommonSizeClass) -> Result<HeapSlot, AllocError> {
-        match class {
-            CommonSizeClass::Bytes8 => self.slab8.alloc(),
-            CommonSizeClass::Bytes16 => self.slab16.alloc(),
-            CommonSizeClass::Bytes32 => self.slab32.alloc(),
-            CommonSizeClass::Bytes64 => self.slab64.alloc(),
-            CommonSizeClass::Bytes128 => self.slab128.alloc(),
-            CommonSizeClass::Bytes256 => self.slab256.alloc(),
-            CommonSizeClass::Bytes512 => self.slab512.alloc(),
-            CommonSizeClass::Bytes1024 => self.slab1024.alloc(),
-            CommonSizeClass::Bytes2048 => self.slab2048.alloc(),
-        }
+    fn is_empty(&self) -> bool {
+        self.nr_rounds == 0
     }
 
-    fn dealloc(&mut self, slot: HeapSlot, class: CommonSizeClass) -> Result<(), AllocError> {
-        match class {
-            CommonSizeClass::Bytes8 => self.slab8.dealloc(slot),
-            CommonSizeClass::Bytes16 => self.slab16.dealloc(slot),
-            CommonSizeClass::Bytes32 => self.slab32.dealloc(slot),
-            CommonSizeClass::Bytes64 => self.slab64.dealloc(slot),
-            CommonSizeClass::Bytes128 => self.slab128.dealloc(slot),
-            CommonSizeClass::Bytes256 => self.slab256.dealloc(slot),
-            CommonSizeClass::Bytes512 => self.slab512.dealloc(slot),
-            CommonSizeClass::Bytes1024 => self.slab1024.dealloc(slot),
-            CommonSizeClass::Bytes2048 => self.slab2048.dealloc(slot),
+    fn is_full(&self) -> bool {
+        self.nr_rounds == Self::CAPACITY
+    }
+
+    fn pop(&mut self) -> Option<HeapSlot> {
+        let slot = self.list.pop()?;
+        self.nr_rounds -= 1;
+        Some(slot)
+    }
+
+    fn push(&mut self, slot: HeapSlot) {
+        debug_assert!(!self.is_full());
+        self.list.push(slot);
+        self.nr_rounds += 1;
+    }
+
+    /// Returns all the slots in the magazine to the slabs.
+    fn flush(&mut self, slabs: &mut SlabCache<SLOT_SIZE>) -> Result<(), AllocError> {
+        while let Some(slot) = self.pop() {
+            slabs.dealloc(slot)?;
         }
+        Ok(())
     }
 }
 
-static GLOBAL_POOL: SpinLock<Heap, LocalIrqDisabled> = SpinLock::new(Heap::new());
+/// The depot of full magazines shared by all CPUs.
+struct Depot<const SLOT_SIZE: usize> {
+    magazines: [Magazine<SLOT_SIZE>; DEPOT_CAPACITY],
+    nr_magazines: usize,
+}
 
-/// The maximum size in bytes of the object cache of each slot size class.
-const OBJ_CACHE_MAX_SIZE: usize = 8 * PAGE_SIZE;
-/// The expected size in bytes of the object cache of each slot size class.
+impl<const SLOT_SIZE: usize> Depot<SLOT_SIZE> {
+    const fn new() -> Self {
+        Self {
+            magazines: [const { Magazine::new() }; DEPOT_CAPACITY],
+            nr_magazines: 0,
+        }
+    }
+
+    fn pop(&mut self) -> Option<Magazine<SLOT_SIZE>> {
+        if self.nr_magazines == 0 {
+            return None;
+        }
+        self.nr_magazines -= 1;
+        Some(core::mem::replace(
+            &mut self.magazines[self.nr_magazines],
+            Magazine::new(),
+        ))
+    }
+
+    /// Pushes a full magazine into the depot.
+    ///
+    /// If the depot is full, the magazine is returned back.
+    fn push(&mut self, magazine: Magazine<SLOT_SIZE>) -> Result<(), Magazine<SLOT_SIZE>> {
+        debug_assert!(magazine.is_full());
+        if self.nr_magazines == DEPOT_CAPACITY {
+            return Err(magazine);
+        }
+        self.magazines[self.nr_magazines] = magazine;
+        self.nr_magazines += 1;
+        Ok(())
+    }
+
+    fn nr_slots(&self) -> usize {
+        self.nr_magazines * Magazine::<SLOT_SIZE>::CAPACITY
+    }
+}
+
+/// The global pool of a slot size class.
 ///
-/// If the cache exceeds the maximum size or is empty, it will be adjusted to
-/// this size.
-const OBJ_CACHE_EXPECTED_SIZE: usize = 2 * PAGE_SIZE;
+/// The slabs and the depot are protected by different locks, so that
+/// exchanging magazines with the depot does not contend with the slabs.
+struct SizeClassPool<const SLOT_SIZE: usize> {
+    slabs: SpinLock<SlabCache<SLOT_SIZE>, LocalIrqDisabled>,
+    depot: SpinLock<Depot<SLOT_SIZE>, LocalIrqDisabled>,
+}
+
+impl<const SLOT_SIZE: usize> SizeClassPool<SLOT_SIZE> {
+    const fn new() -> Self {
+        Self {
+            slabs: SpinLock::new(SlabCache::new()),
+            depot: SpinLock::new(Depot::new()),
+        }
+    }
+
+    /// Fills the magazine with the slots from the slabs.
+    fn fill(&self, magazine: &mut Magazine<SLOT_SIZE>) {
+        let mut slabs = self.slabs.lock();
+        while !magazine.is_full() {
+            let Ok(slot) = slabs.alloc() else {
+                break;
+            };
+            magazine.push(slot);
+        }
+    }
+
+    /// Releases the cached memory of the pool and the local cache.
+    ///
+    /// It does not block on the locks, so it may release nothing.
+    fn shrink(&self, local_cache: Option<&mut ObjectCache<SLOT_SIZE>>) -> usize {
+        let depot = self.depot.try_lock();
+        let Some(mut slabs) = self.slabs.try_lock() else {
+            return 0;
+        };
 
+        // The errors are already reported by the slab cache.
+        if let Some(local_cache) = local_cache {
+            let _ = local_cache.loaded.flush(&mut slabs);
+            let _ = local_cache.previous.flush(&mut slabs);
+        }
+        if let Some(mut depot) = depot {
+            while let Some(mut magazine) = depot.pop() {
+                let _ = magazine.flush(&mut slabs);
+            }
+        }
+
+        slabs.shrink()
+    }
+
+    fn stats(&self) -> SlabCacheStats {
+        let nr_depot_slots = self.depot.lock().nr_slots();
+        let slabs = self.slabs.lock();
+        let slots_per_slab = SlabCache::<SLOT_SIZE>::slots_per_slab();
+
+        SlabCacheStats {
+            slot_size: SLOT_SIZE,
+            nr_active_slots: slabs.nr_allocated(),
+            nr_slots: slabs.nr_slabs() * slots_per_slab,
+            slots_per_slab,
+            nr_active_slabs: slabs.nr_active_slabs(),
+            nr_slabs: slabs.nr_slabs(),
+            magazine_capacity: Magazine::<SLOT_SIZE>::CAPACITY,
+            nr_depot_slots,
+        }
+    }
+}
+
+struct Heap {
+    pool8: SizeClassPool<8>,
+    pool16: SizeClassPool<16>,
+    pool32: SizeClassPool<32>,
+    pool64: SizeClassPool<64>,
+    pool128: SizeClassPool<128>,
+    pool256: SizeClassPool<256>,
+    pool512: SizeClassPool<512>,
+    pool1024: SizeClassPool<1024>,
+    pool2048: SizeClassPool<2048>,
+}
+
+impl Heap {
+    const fn new() -> Self {
+        Self {
+            pool8: SizeClassPool::new(),
+            pool16: SizeClassPool::new(),
+            pool32: SizeClassPool::new(),
+            pool64: SizeClassPool::new(),
+            pool128: SizeClassPool::new(),
+            pool256: SizeClassPool::new(),
+            pool512: SizeClassPool::new(),
+            pool1024: SizeClassPool::new(),
+            pool2048: SizeClassPool::new(),
+        }
+    }
+
+    fn shrink(&self, mut local: Option<&mut LocalCache>) -> usize {
+        self.pool8
+            .shrink(local.as_mut().map(|cache| &mut cache.cache8))
+            + self
+                .pool16
+                .shrink(local.as_mut().map(|cache| &mut cache.cache16))
+            + self
+                .pool32
+                .shrink(local.as_mut().map(|cache| &mut cache.cache32))
+            + self
+                .pool64
+                .shrink(local.as_mut().map(|cache| &mut cache.cache64))
+            + self
+                .pool128
+                .shrink(local.as_mut().map(|cache| &mut cache.cache128))
+            + self
+                .pool256
+                .shrink(local.as_mut().map(|cache| &mut cache.cache256))
+            + self
+                .pool512
+                .shrink(local.as_mut().map(|cache| &mut cache.cache512))
+            + self
+                .pool1024
+                .shrink(local.as_mut().map(|cache| &mut cache.cache1024))
+            + self
+                .pool2048
+                .shrink(local.as_mut().map(|cache| &mut cache.cache2048))
+    }
+
+    fn stats(&self) -> Vec<SlabCacheStats> {
+        vec![
+            self.pool8.stats(),
+            self.pool16.stats(),
+            self.pool32.stats(),
+            self.pool64.stats(),
+            self.pool128.stats(),
+            self.pool256.stats(),
+            self.pool512.stats(),
+            self.pool1024.stats(),
+            self.pool2048.stats(),
+        ]
+    }
+}
+
+static GLOBAL_POOL: Heap = Heap::new();
+
+/// The per-CPU object cache of a slot size class.
+///
+/// It follows the magazine design: a CPU allocates from and deallocates to
+/// its loaded magazine without any locks. The previous magazine, which is
+/// either full or empty, is swapped in if the loaded one runs out. Only if
+/// both run out, full magazines are exchanged with the depot, which then
+/// falls back to the slabs.
 struct ObjectCache<const SLOT_SIZE: usize> {
-    list: SlabSlotList<SLOT_SIZE>,
-    list_size: usize,
+    loaded: Magazine<SLOT_SIZE>,
+    previous: Magazine<SLOT_SIZE>,
 }
 
 impl<const SLOT_SIZE: usize> ObjectCache<SLOT_SIZE> {
     const fn new() -> Self {
         Self {
-            list: SlabSlotList::new(),
-            list_size: 0,
+            loaded: Magazine::new(),
+            previous: Magazine::new(),
         }
     }
 
-    fn alloc(&mut self) -> Result<HeapSlot, AllocError> {
-        if let Some(slot) = self.list.pop() {
-            self.list_size -= SLOT_SIZE;
+    fn alloc(&mut self, pool: &SizeClassPool<SLOT_SIZE>) -> Result<HeapSlot, AllocError> {
+        if let Some(slot) = self.loaded.pop() {
             return Ok(slot);
         }
 
-        let size_class = CommonSizeClass::from_size(SLOT_SIZE).unwrap();
-        let mut global_pool = GLOBAL_POOL.lock();
-        for _ in 0..OBJ_CACHE_EXPECTED_SIZE / SLOT_SIZE {
-            if let Ok(slot) = global_pool.alloc(size_class) {
-                self.list.push(slot);
-                self.list_size += SLOT_SIZE;
+        if self.previous.is_full() {
+            core::mem::swap(&mut self.loaded, &mut self.previous);
+        } else {
+            let full = pool.depot.lock().pop();
+            if let Some(full) = full {
+                self.loaded = full;
             } else {
-                break;
+                pool.fill(&mut self.loaded);
             }
         }
 
-        if let Ok(new_slot) = global_pool.alloc(size_class) {
-            Ok(new_slot)
-        } else if let Some(popped) = self.list.pop() {
-            self.list_size -= SLOT_SIZE;
-            Ok(popped)
-        } else {
-            Err(AllocError)
-        }
+        self.loaded.pop().ok_or(AllocError)
     }
 
-    fn dealloc(&mut self, slot: HeapSlot, class: CommonSizeClass) -> Result<(), AllocError> {
-        if self.list_size + SLOT_SIZE < OBJ_CACHE_MAX_SIZE {
-            self.list.push(slot);
-            self.list_size += SLOT_SIZE;
+    fn dealloc(
+        &mut self,
+        slot: HeapSlot,
+        pool: &SizeClassPool<SLOT_SIZE>,
+    ) -> Result<(), AllocError> {
+        if !self.loaded.is_full() {
+            self.loaded.push(slot);
             return Ok(());
         }
 
-        let mut global_pool = GLOBAL_POOL.lock();
-        global_pool.dealloc(slot, class)?;
-        for _ in 0..(self.list_size - OBJ_CACHE_EXPECTED_SIZE) / SLOT_SIZE {
-            let slot = self.list.pop().expect("The cache size should be ample");
-            global_pool.dealloc(slot, class)?;
-            self.list_size -= SLOT_SIZE;
+        if !self.previous.is_empty() {
+            let previous = core::mem::replace(&mut self.previous, Magazine::new());
+            let rejected = pool.depot.lock().push(previous);
+            if let Err(mut previous) = rejected {
+                previous.flush(&mut pool.slabs.lock())?;
+            }
         }
+        core::mem::swap(&mut self.loaded, &mut self.previous);
 
+        self.loaded.push(slot);
         Ok(())
     }
 }
@@ -250,30 +449,32 @@ impl LocalCache {
     }
 
     fn alloc(&mut self, class: CommonSizeClass) -> Result<HeapSlot, AllocError> {
+        let pool = &GLOBAL_POOL;
         match class {
-            CommonSizeClass::Bytes8 => self.cache8.alloc(),
-            CommonSizeClass::Bytes16 => self.cache16.alloc(),
-            CommonSizeClass::Bytes32 => self.cache32.alloc(),
-            CommonSizeClass::Bytes64 => self.cache64.alloc(),
-            CommonSizeClass::Bytes128 => self.cache128.alloc(),
-            CommonSizeClass::Bytes256 => self.cache256.alloc(),
-            CommonSizeClass::Bytes512 => self.cache512.alloc(),
-            CommonSizeClass::Bytes1024 => self.cache1024.alloc(),
-            CommonSizeClass::Bytes2048 => self.cache2048.alloc(),
+            CommonSizeClass::Bytes8 => self.cache8.alloc(&pool.pool8),
+            CommonSizeClass::Bytes16 => self.cache16.alloc(&pool.pool16),
+            CommonSizeClass::Bytes32 => self.cache32.alloc(&pool.pool32),
+            CommonSizeClass::Bytes64 => self.cache64.alloc(&pool.pool64),
+            CommonSizeClass::Bytes128 => self.cache128.alloc(&pool.pool128),
+            CommonSizeClass::Bytes256 => self.cache256.alloc(&pool.pool256),
+            CommonSizeClass::Bytes512 => self.cache512.alloc(&pool.pool512),
+            CommonSizeClass::Bytes1024 => self.cache1024.alloc(&pool.pool1024),
+            CommonSizeClass::Bytes2048 => self.cache2048.alloc(&pool.pool2048),
         }
     }
 
     fn dealloc(&mut self, slot: HeapSlot, class: CommonSizeClass) -> Result<(), AllocError> {
+        let pool = &GLOBAL_POOL;
         match class {
-            CommonSizeClass::Bytes8 => self.cache8.dealloc(slot, class),
-            CommonSizeClass::Bytes16 => self.cache16.dealloc(slot, class),
-            CommonSizeClass::Bytes32 => self.cache32.dealloc(slot, class),
-            CommonSizeClass::Bytes64 => self.cache64.dealloc(slot, class),
-            CommonSizeClass::Bytes128 => self.cache128.dealloc(slot, class),
-            CommonSizeClass::Bytes256 => self.cache256.dealloc(slot, class),
-            CommonSizeClass::Bytes512 => self.cache512.dealloc(slot, class),
-            CommonSizeClass::Bytes1024 => self.cache1024.dealloc(slot, class),
-            CommonSizeClass::Bytes2048 => self.cache2048.dealloc(slot, class),
+            CommonSizeClass::Bytes8 => self.cache8.dealloc(slot, &pool.pool8),
+            CommonSizeClass::Bytes16 => self.cache16.dealloc(slot, &pool.pool16),
+            CommonSizeClass::Bytes32 => self.cache32.dealloc(slot, &pool.pool32),
+            CommonSizeClass::Bytes64 => self.cache64.dealloc(slot, &pool.pool64),
+            CommonSizeClass::Bytes128 => self.cache128.dealloc(slot, &pool.pool128),
+            CommonSizeClass::Bytes256 => self.cache256.dealloc(slot, &pool.pool256),
+            CommonSizeClass::Bytes512 => self.cache512.dealloc(slot, &pool.pool512),
+            CommonSizeClass::Bytes1024 => self.cache1024.dealloc(slot, &pool.pool1024),
+            CommonSizeClass::Bytes2048 => self.cache2048.dealloc(slot, &pool.pool2048),
         }
     }
 }
@@ -282,6 +483,11 @@ cpu_local! {
     static LOCAL_POOL: RefCell<LocalCache> = RefCell::new(LocalCache::new());
 }
 
+/// Gets the statistics of the slab caches of all the slot size classes.
+pub fn slab_cache_stats() -> Vec<SlabCacheStats> {
+    GLOBAL_POOL.stats()
+}
+
 /// The global heap allocator provided by OSDK.
 ///
 /// It is a singleton that provides heap allocation for the kernel. If
@@ -314,4 +520,45 @@ impl GlobalHeapAllocator for HeapAllocator {
 
         local_cache.dealloc(slot, class)
     }
+
+    fn shrink(&self) -> usize {
+        // The magazines of other CPUs are left intact. The local cache is
+        // busy if the memory pressure comes from the allocator itself.
+        let irq_guard = irq::disable_local();
+        let this_cache = LOCAL_POOL.get_with(&irq_guard);
+        let mut local_cache = this_cache.try_borrow_mut().ok();
+
+        GLOBAL_POOL.shrink(local_cache.as_deref_mut())
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use alloc::boxed::Box;
+
+    use ostd::prelude::ktest;
+
+    use super::*;
+
+    #[ktest]
+    fn alloc_dealloc_across_magazines() {
+        // Allocating more slots than two magazines can hold forces exchanges
+        // with the depot and the slabs.
+        let nr_slots = Magazine::<64>::CAPACITY * 4;
+        let boxes = (0..nr_slots)
+            .map(|i| Box::new([i as u8; 64]))
+            .collect::<Vec<_>>();
+        for (i, allocated) in boxes.iter().enumerate() {
+            assert!(allocated.iter().all(|byte| *byte == i as u8));
+        }
+        drop(boxes);
+
+        let stats = slab_cache_stats()
+            .into_iter()
+            .find(|stats| stats.slot_size == 64)
+            .unwrap();
+        assert!(stats.nr_active_slots <= stats.nr_slots);
+        assert!(stats.nr_active_slabs <= stats.nr_slabs);
+        assert!(stats.nr_depot_slots <= DEPOT_CAPACITY * stats.magazine_capacity);
+    }
 }
@@ -10,5 +10,5 @@ mod allocator;
 mod cpu_local_allocator;
 mod slab_cache;
 
-pub use allocator::{HeapAllocator, type_from_layout};
+pub use allocator::{HeapAllocator, SlabCacheStats, slab_cache_stats, type_from_layout};
 pub use cpu_local_allocator::{CpuLocalBox, alloc_cpu_local};
@@ -13,6 +13,10 @@ use ostd::mm::{
 const EXPECTED_EMPTY_SLABS: usize = 4;
 const MAX_EMPTY_SLABS: usize = 16;
 
+/// The granularity of the offsets of the first slots of slabs, which is the
+/// size of a cache line.
+const STAGGER_ALIGN: usize = 64;
+
 /// A slab cache.
 ///
 /// A slab cache contains 3 parts:
@@ -21,10 +25,20 @@ const MAX_EMPTY_SLABS: usize = 16;
 ///  - and a list of full slabs.
 ///
 /// So the cache is partially sorted, to allow caching and reusing memory.
+///
+/// New slabs start allocating from different offsets in turn (see
+/// [`Slab::new_staggered`]) so that the objects allocated first from
+/// different slabs spread over the cache sets. This staggers only the
+/// allocation order. It is not cache coloring, since full slabs still use
+/// the same offsets.
 pub struct SlabCache<const SLOT_SIZE: usize> {
     empty: LinkedList<SlabMeta<SLOT_SIZE>>,
     partial: LinkedList<SlabMeta<SLOT_SIZE>>,
     full: LinkedList<SlabMeta<SLOT_SIZE>>,
+    /// The offset of the first slot of the next new slab.
+    next_first_offset: usize,
+    /// The number of slots allocated from the slabs.
+    nr_allocated: usize,
 }
 
 impl<const SLOT_SIZE: usize> SlabCache<SLOT_SIZE> {
@@ -34,9 +48,40 @@ impl<const SLOT_SIZE: usize> SlabCache<SLOT_SIZE> {
             empty: LinkedList::new(),
             partial: LinkedList::new(),
             full: LinkedList::new(),
+            next_first_offset: 0,
+            nr_allocated: 0,
         }
     }
 
+    /// Returns the number of slots allocated from the slabs.
+    ///
+    /// The slots cached in the upper layers are also counted.
+    pub fn nr_allocated(&self) -> usize {
+        self.nr_allocated
+    }
+
+    /// Returns the number of slabs that have allocated slots.
+    pub fn nr_active_slabs(&self) -> usize {
+        self.partial.size() + self.full.size()
+    }
+
+    /// Returns the total number of slabs.
+    pub fn nr_slabs(&self) -> usize {
+        self.empty.size() + self.partial.size() + self.full.size()
+    }
+
+    /// Returns the number of slots in a slab.
+    pub const fn slots_per_slab() -> usize {
+        PAGE_SIZE / SLOT_SIZE
+    }
+
+    /// Frees all the empty slabs and returns the number of them.
+    pub fn shrink(&mut self) -> usize {
+        let nr_freed = self.empty.size();
+        while self.empty.pop_front().is_some() {}
+        nr_freed
+    }
+
     /// Allocates a slot from the cache.
     ///
     /// The caller must provide which cache is it because we don't know from
@@ -50,6 +95,7 @@ impl<const SLOT_SIZE: usize> SlabCache<SLOT_SIZE> {
             if current.nr_allocated() == current.capacity() {
                 self.full.push_front(cursor.take_current().unwrap());
             }
+            self.nr_allocated += 1;
             return Ok(allocated);
         }
 
@@ -58,20 +104,22 @@ impl<const SLOT_SIZE: usize> SlabCache<SLOT_SIZE> {
             let mut slab = self.empty.pop_front().unwrap();
             let allocated = slab.meta_mut().alloc().unwrap();
             self.add_slab(slab);
+            self.nr_allocated += 1;
             return Ok(allocated);
         }
 
         // If no empty slab is available, allocate new slabs.
-        let Ok(mut allocated_empty) = Slab::new() else {
+        let Ok(mut allocated_empty) = self.new_slab() else {
             log::error!("Failed to allocate a new slab");
             return Err(AllocError);
         };
         let allocated = allocated_empty.meta_mut().alloc().unwrap();
         self.add_slab(allocated_empty);
+        self.nr_allocated += 1;
 
         // Allocate more empty slabs and push them into the cache.
         for _ in 0..EXPECTED_EMPTY_SLABS {
-            if let Ok(allocated_empty) = Slab::new() {
+            if let Ok(allocated_empty) = self.new_slab() {
                 self.empty.push_front(allocated_empty);
             } else {
                 break;
@@ -103,9 +151,10 @@ impl<const SLOT_SIZE: usize> SlabCache<SLOT_SIZE> {
             AllocError
         })?;
 
-        slab.dealloc(slot)?;
-
+        let result = slab.dealloc(slot);
         self.add_slab(slab);
+        result?;
+        self.nr_allocated -= 1;
 
         // If the slab cache has too many empty slabs, free some of them.
         if self.empty.size() > MAX_EMPTY_SLABS {
@@ -117,6 +166,13 @@ impl<const SLOT_SIZE: usize> SlabCache<SLOT_SIZE> {
         Ok(())
     }
 
+    fn new_slab(&mut self) -> ostd::Result<Slab<SLOT_SIZE>> {
+        let slab = Slab::new_staggered(self.next_first_offset)?;
+        self.next_first_offset =
+            (self.next_first_offset + STAGGER_ALIGN.max(SLOT_SIZE)) % PAGE_SIZE;
+        Ok(slab)
+    }
+
     fn add_slab(&mut self, slab: Slab<SLOT_SIZE>) {
         if slab.meta().nr_allocated() == slab.meta().capacity() {
             self.full.push_front(slab);
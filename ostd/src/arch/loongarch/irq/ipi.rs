@@ -13,6 +13,11 @@ impl HwCpuId {
         // TODO: Support SMP in LoongArch.
         Self(0)
     }
+
+    /// Returns the raw value of the ID.
+    pub(crate) fn as_u32(self) -> u32 {
+        self.0
+    }
 }
 
 /// Sends a general inter-processor interrupt (IPI) to the specified CPU.
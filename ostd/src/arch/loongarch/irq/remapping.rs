@@ -1,5 +1,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use super::HwCpuId;
+
 pub(crate) struct IrqRemapping {
     _private: (),
 }
@@ -15,6 +17,12 @@ impl IrqRemapping {
     /// remapping is disabled or not supported by the architecture.
     pub(crate) fn init(&self, _irq_num: u8) {}
 
+    /// Routes the remapped IRQ to the specific CPU.
+    ///
+    /// This will do nothing if interrupt remapping is disabled or not supported by the
+    /// architecture.
+    pub(crate) fn set_destination(&self, _irq_num: u8, _hw_cpu_id: HwCpuId) {}
+
     /// Gets the remapping index of the IRQ line.
     ///
     /// This method will return `None` if interrupt remapping is disabled or
@@ -13,7 +13,7 @@ pub(crate) mod irq;
 pub(crate) mod mm;
 pub mod serial;
 pub(crate) mod task;
-mod timer;
+pub(crate) mod timer;
 pub mod trap;
 
 #[cfg(feature = "cvm_guest")]
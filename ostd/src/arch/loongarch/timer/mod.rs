@@ -8,4 +8,25 @@ use super::trap::TrapFrame;
 #[expect(dead_code)]
 fn timer_callback(trapframe: &TrapFrame) {
     crate::timer::call_timer_callback_functions(trapframe);
+    crate::timer::expire_event();
+}
+
+/// Programs the timer to fire at the next tick or the next one-shot event, whichever is earlier.
+///
+/// The caller should disable local IRQs.
+pub(crate) fn program_next_interrupt() {
+    // TODO: Program the timer once LoongArch timer support is added. Until then, one-shot events
+    // never expire.
+}
+
+/// Returns whether the tick can be stopped on idle CPUs.
+pub(crate) fn can_stop_tick() -> bool {
+    false
+}
+
+/// Restarts the tick after it is stopped.
+///
+/// Returns the number of ticks that have been skipped.
+pub(crate) fn restart_tick() -> u64 {
+    0
 }
@@ -14,7 +14,7 @@ pub(crate) mod mm;
 mod power;
 pub(crate) mod serial;
 pub(crate) mod task;
-mod timer;
+pub(crate) mod timer;
 pub mod trap;
 
 #[cfg(feature = "cvm_guest")]
@@ -11,6 +11,7 @@ use spin::Once;
 
 use crate::{
     arch::{self, boot::DEVICE_TREE, cpu::extension::IsaExtensions, trap::TrapFrame},
+    cpu_local_cell,
     irq::IrqLine,
     timer::TIMER_FREQ,
 };
@@ -20,6 +21,11 @@ pub(super) static TIMER_IRQ: Once<IrqLine> = Once::new();
 static TIMEBASE_FREQ: AtomicU64 = AtomicU64::new(0);
 static TIMER_INTERVAL: AtomicU64 = AtomicU64::new(0);
 
+cpu_local_cell! {
+    /// The time value at which the next tick is due on this hart.
+    static TICK_DEADLINE: u64 = 0;
+}
+
 /// Initializes the timer module on the BSP.
 ///
 /// # Safety
@@ -81,7 +87,8 @@ pub(super) unsafe fn init_on_ap() {
 ///
 /// This function must be called on a hart that hasn't called this function.
 unsafe fn init_current_hart() {
-    set_next_timer();
+    update_tick();
+    program_next_interrupt();
     // SAFETY: Accessing the `sie` CSR to enable the timer interrupt is safe
     // here because this function is only called during timer initialization,
     // and we ensure that only the timer interrupt bit is set without affecting
@@ -92,33 +99,78 @@ unsafe fn init_current_hart() {
 }
 
 fn timer_callback(trapframe: &TrapFrame) {
-    crate::timer::call_timer_callback_functions(trapframe);
+    if update_tick() {
+        crate::timer::call_timer_callback_functions(trapframe);
+    }
+    crate::timer::expire_event();
 
-    set_next_timer();
+    program_next_interrupt();
 }
 
-fn set_next_timer() {
+/// Checks whether a tick is due on timer interrupt.
+///
+/// The timer interrupt may be raised for a one-shot event before the next tick is due. If the
+/// tick is due, this function also sets the deadline of the next tick.
+fn update_tick() -> bool {
+    let current = riscv::register::time::read64();
+    if crate::timer::is_tick_stopped() || current < TICK_DEADLINE.load() {
+        return false;
+    }
+
+    let interval = TIMER_INTERVAL.load(Ordering::Relaxed);
+    TICK_DEADLINE.store(current + interval);
+    true
+}
+
+/// Programs the timer to fire at the next tick or the next one-shot event, whichever is earlier.
+///
+/// The caller should disable local IRQs.
+pub(crate) fn program_next_interrupt() {
+    let deadline = crate::timer::next_interrupt_deadline(TICK_DEADLINE.load());
+
     // SAFETY: Calling the `SET_NEXT_TIMER_FN` function pointer is safe here
     // because we ensure that it is set to a valid function during the timer
     // initialization, and we never modify it after that.
     unsafe {
-        SET_NEXT_TIMER_FN();
+        SET_NEXT_TIMER_FN(deadline);
     }
 }
 
-static mut SET_NEXT_TIMER_FN: fn() = set_next_timer_sbi;
+/// Returns whether the tick can be stopped on idle harts.
+pub(crate) fn can_stop_tick() -> bool {
+    true
+}
 
-fn set_next_timer_sbi() {
-    sbi_rt::set_timer(get_next_when());
+/// Restarts the tick after it is stopped.
+///
+/// Returns the number of ticks that have been skipped.
+///
+/// The caller should disable local IRQs.
+pub(crate) fn restart_tick() -> u64 {
+    let current = riscv::register::time::read64();
+    let interval = TIMER_INTERVAL.load(Ordering::Relaxed);
+    let tick_deadline = TICK_DEADLINE.load();
+    let skipped_ticks = current.saturating_sub(tick_deadline) / interval;
+    // The last skipped tick is due now, so it will be handled immediately.
+    TICK_DEADLINE.store(tick_deadline + skipped_ticks * interval);
+    program_next_interrupt();
+
+    skipped_ticks
+}
+
+static mut SET_NEXT_TIMER_FN: fn(u64) = set_next_timer_sbi;
+
+fn set_next_timer_sbi(deadline: u64) {
+    sbi_rt::set_timer(deadline);
 }
 
-fn set_next_timer_sstc() {
+fn set_next_timer_sstc(deadline: u64) {
     // SAFETY: Setting the next timer using the `stimecmp` CSR is safe here
-    // because we are using the `stimecmp` CSR to set the next timer interrupt
-    // only when we're handling a timer interrupt, which is a standard operation
-    // specified by RISC-V SSTC extension.
+    // because the `stimecmp` CSR only determines when the next timer interrupt
+    // is raised on the current hart, which is a standard operation specified by
+    // RISC-V SSTC extension.
     unsafe {
-        asm!("csrrw {}, stimecmp, {}", out(reg) _, in(reg) get_next_when());
+        asm!("csrrw {}, stimecmp, {}", out(reg) _, in(reg) deadline);
     }
 }
 
@@ -126,12 +178,6 @@ fn is_sstc_enabled() -> bool {
     arch::cpu::extension::has_extensions(IsaExtensions::SSTC)
 }
 
-fn get_next_when() -> u64 {
-    let current = riscv::register::time::read64();
-    let interval = TIMER_INTERVAL.load(Ordering::Relaxed);
-    current + interval
-}
-
 pub(crate) fn get_timebase_freq() -> u64 {
     TIMEBASE_FREQ.load(Ordering::Relaxed)
 }
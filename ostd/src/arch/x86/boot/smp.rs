@@ -133,13 +133,18 @@ pub(crate) unsafe fn bringup_all_aps(info_ptr: *const PerApRawInfo, pt_ptr: Padd
 
 /// This is where the linker load the symbols in the `.ap_boot` section.
 /// The BSP would copy the AP boot code to this address.
-const AP_BOOT_START_PA: usize = 0x8000;
+///
+/// The AP boot code starts in real mode at this address, so it also serves as
+/// the firmware waking vector when the system wakes up from a sleeping state.
+pub(in crate::arch) const AP_BOOT_START_PA: usize = 0x8000;
 
 /// The size of the AP boot code (the `.ap_boot` section).
 fn ap_boot_code_size() -> usize {
     __ap_boot_end as *const () as usize - __ap_boot_start as *const () as usize
 }
 
+// TODO: The AP boot code is still used after booting because the system wakes
+// up from sleeping states via it. It must not be reclaimed then.
 pub(super) fn reclaimable_memory_region() -> MemoryRegion {
     MemoryRegion::new(
         AP_BOOT_START_PA,
@@ -185,11 +190,13 @@ unsafe fn fill_boot_info_ptr(info_ptr: *const PerApRawInfo) {
     }
 }
 
+/// Fills the page table used by the AP boot code.
+///
 /// # Safety
 ///
 /// This function writes to the static mutable variable `__boot_page_table_pointer`.
 /// The caller must ensure exclusive access to this variable.
-unsafe fn fill_boot_pt_ptr(pt_ptr: Paddr) {
+pub(in crate::arch) unsafe fn fill_boot_pt_ptr(pt_ptr: Paddr) {
     unsafe extern "C" {
         static mut __boot_page_table_pointer: u32;
     }
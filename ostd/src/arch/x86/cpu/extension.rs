@@ -105,6 +105,7 @@ define_isa_extensions! {
         X2APIC,       Ecx(21), "The processor supports x2APIC feature.";
         TSC_DEADLINE, Ecx(24), "The processor's local APIC timer supports \
                                 one-shot operation using a TSC deadline value.";
+        AES,          Ecx(25), "The processor supports the AES instruction extensions.";
         XSAVE,        Ecx(26), "The processor supports the XSAVE/XRSTOR \
                                 processor extended states feature, \
                                 the XSETBV/XGETBV instructions, and XCR0.";
@@ -117,5 +118,10 @@ define_isa_extensions! {
     leaf 7, subleaf 0 => {
         FSGSBASE,     Ebx( 0), "Supports RDFSBASE/RDGSBASE/WRFSBASE/WRGSBASE.";
         AVX512F,      Ebx(16), "Supports the AVX512F instruction extensions.";
+        RDPID,        Ecx(22), "Supports the RDPID instruction.";
+    }
+
+    leaf 0x8000_0001, subleaf 0 => {
+        RDTSCP,       Edx(27), "Supports the RDTSCP instruction and IA32_TSC_AUX.";
     }
 }
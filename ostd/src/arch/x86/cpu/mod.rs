@@ -6,3 +6,5 @@ pub mod context;
 pub mod cpuid;
 pub mod extension;
 pub mod local;
+pub mod pmu;
+pub mod pstate;
@@ -0,0 +1,262 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The architectural performance monitoring unit (PMU).
+//!
+//! The architectural PMU provides general-purpose counters, each of which can be programmed to
+//! count one of the architectural events. Its version, the number of the counters, and the
+//! available events are enumerated by the CPUID instruction.
+//!
+//! Reference: Intel(R) 64 and IA-32 Architectures Software Developer's Manual, Section 21.2,
+//! Architectural Performance Monitoring.
+
+use spin::Once;
+use x86::msr::{rdmsr, wrmsr};
+
+use super::cpuid::cpuid;
+use crate::{
+    cpu::{CpuId, PinCurrentCpu},
+    cpu_local_cell,
+    irq::{DisabledLocalIrqGuard, disable_local},
+};
+
+/// The CPUID leaf of the architectural performance monitoring.
+const ARCH_PERF_MON_LEAF: u32 = 0x0a;
+
+const IA32_PMC0: u32 = 0xc1;
+const IA32_PERFEVTSEL0: u32 = 0x186;
+const IA32_PERF_GLOBAL_CTRL: u32 = 0x38f;
+
+/// The bits in the `IA32_PERFEVTSELx` MSRs.
+const EVTSEL_USR: u64 = 1 << 16;
+const EVTSEL_OS: u64 = 1 << 17;
+const EVTSEL_EN: u64 = 1 << 22;
+
+/// An architectural performance event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchPerfEvent {
+    /// The unhalted core cycles.
+    CoreCycles,
+    /// The retired instructions.
+    InstructionsRetired,
+    /// The unhalted reference cycles.
+    ReferenceCycles,
+    /// The references to the last level cache.
+    LlcReferences,
+    /// The misses of the last level cache.
+    LlcMisses,
+    /// The retired branch instructions.
+    BranchInstructionsRetired,
+    /// The retired branch instructions that are mispredicted.
+    BranchMissesRetired,
+}
+
+impl ArchPerfEvent {
+    /// Returns the event select and the unit mask of the event.
+    const fn select_and_umask(self) -> (u8, u8) {
+        match self {
+            Self::CoreCycles => (0x3c, 0x00),
+            Self::InstructionsRetired => (0xc0, 0x00),
+            Self::ReferenceCycles => (0x3c, 0x01),
+            Self::LlcReferences => (0x2e, 0x4f),
+            Self::LlcMisses => (0x2e, 0x41),
+            Self::BranchInstructionsRetired => (0xc4, 0x00),
+            Self::BranchMissesRetired => (0xc5, 0x00),
+        }
+    }
+
+    /// Returns the bit in `CPUID.0AH:EBX` that indicates the event is not available.
+    const fn cpuid_bit(self) -> u32 {
+        self as u32
+    }
+}
+
+/// The information about the architectural PMU.
+#[derive(Debug)]
+pub struct PmuInfo {
+    version: u8,
+    nr_counters: u8,
+    counter_width: u8,
+    /// The number of the events enumerated in `unavailable_events`.
+    nr_events: u8,
+    unavailable_events: u32,
+}
+
+impl PmuInfo {
+    /// Returns the version of the architectural PMU.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Returns the number of the general-purpose counters on each CPU.
+    pub fn nr_counters(&self) -> u8 {
+        self.nr_counters
+    }
+
+    /// Returns the bit width of the general-purpose counters.
+    pub fn counter_width(&self) -> u8 {
+        self.counter_width
+    }
+
+    /// Returns whether the event can be counted.
+    pub fn is_available(&self, event: ArchPerfEvent) -> bool {
+        let bit = event.cpuid_bit();
+        bit < self.nr_events as u32 && self.unavailable_events & (1 << bit) == 0
+    }
+}
+
+static PMU_INFO: Once<Option<PmuInfo>> = Once::new();
+
+/// Returns the information about the architectural PMU.
+///
+/// This method will return `None` if the architectural PMU is not supported.
+pub fn pmu_info() -> Option<&'static PmuInfo> {
+    PMU_INFO
+        .call_once(|| {
+            let result = cpuid(ARCH_PERF_MON_LEAF, 0)?;
+            let [version, nr_counters, counter_width, nr_events] = result.eax.to_le_bytes();
+            if version == 0 || nr_counters == 0 {
+                return None;
+            }
+
+            Some(PmuInfo {
+                version,
+                // The counters are tracked in a 32-bit bitmap.
+                nr_counters: nr_counters.min(u32::BITS as u8),
+                counter_width,
+                nr_events,
+                unavailable_events: result.ebx,
+            })
+        })
+        .as_ref()
+}
+
+cpu_local_cell! {
+    /// The bitmap of the general-purpose counters that are in use on this CPU.
+    static USED_COUNTERS: u32 = 0;
+}
+
+/// A general-purpose counter that counts an architectural event on a CPU.
+///
+/// The counter keeps counting until it is dropped. It must be dropped on the CPU where it is
+/// started.
+#[derive(Debug)]
+pub struct PerfCounter {
+    index: u32,
+    cpu: CpuId,
+}
+
+impl PerfCounter {
+    /// Starts counting the event with a free general-purpose counter on the current CPU.
+    ///
+    /// The event is counted in user mode if `count_user` is true, and in kernel mode if
+    /// `count_kernel` is true.
+    ///
+    /// This method will return `None` if the event is not available or all the counters are in
+    /// use.
+    pub fn start(
+        event: ArchPerfEvent,
+        count_user: bool,
+        count_kernel: bool,
+        irq_guard: &DisabledLocalIrqGuard,
+    ) -> Option<Self> {
+        let pmu_info = pmu_info()?;
+        if !pmu_info.is_available(event) {
+            return None;
+        }
+
+        let used_counters = USED_COUNTERS.load();
+        let index = used_counters.trailing_ones();
+        if index >= pmu_info.nr_counters() as u32 {
+            return None;
+        }
+        USED_COUNTERS.store(used_counters | (1 << index));
+
+        let (select, umask) = event.select_and_umask();
+        let mut evtsel = select as u64 | ((umask as u64) << 8) | EVTSEL_EN;
+        if count_user {
+            evtsel |= EVTSEL_USR;
+        }
+        if count_kernel {
+            evtsel |= EVTSEL_OS;
+        }
+
+        // SAFETY: The counter is not used by others, so programming it has no side effects.
+        unsafe {
+            wrmsr(IA32_PMC0 + index, 0);
+            wrmsr(IA32_PERFEVTSEL0 + index, evtsel);
+            if pmu_info.version() >= 2 {
+                let global_ctrl = rdmsr(IA32_PERF_GLOBAL_CTRL);
+                wrmsr(IA32_PERF_GLOBAL_CTRL, global_ctrl | (1 << index));
+            }
+        }
+
+        Some(Self {
+            index,
+            cpu: irq_guard.current_cpu(),
+        })
+    }
+
+    /// Returns the CPU where the counter is counting.
+    pub fn cpu(&self) -> CpuId {
+        self.cpu
+    }
+
+    /// Reads the value of the counter.
+    ///
+    /// The value has [`PmuInfo::counter_width`] bits and wraps around on overflows.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if it is not called on the CPU where the counter is counting.
+    pub fn read(&self, irq_guard: &DisabledLocalIrqGuard) -> u64 {
+        assert_eq!(irq_guard.current_cpu(), self.cpu);
+
+        // SAFETY: Reading a counter has no side effects.
+        unsafe { rdmsr(IA32_PMC0 + self.index) }
+    }
+}
+
+impl Drop for PerfCounter {
+    fn drop(&mut self) {
+        let irq_guard = disable_local();
+        assert_eq!(irq_guard.current_cpu(), self.cpu);
+
+        // SAFETY: The counter is owned by us, so stopping it has no side effects.
+        unsafe {
+            wrmsr(IA32_PERFEVTSEL0 + self.index, 0);
+            if pmu_info().unwrap().version() >= 2 {
+                let global_ctrl = rdmsr(IA32_PERF_GLOBAL_CTRL);
+                wrmsr(IA32_PERF_GLOBAL_CTRL, global_ctrl & !(1 << self.index));
+            }
+        }
+
+        USED_COUNTERS.store(USED_COUNTERS.load() & !(1 << self.index));
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use super::*;
+    use crate::prelude::*;
+
+    #[ktest]
+    fn count_instructions() {
+        let irq_guard = disable_local();
+        let Some(counter) = PerfCounter::start(
+            ArchPerfEvent::InstructionsRetired,
+            false,
+            true,
+            &irq_guard,
+        ) else {
+            // The architectural PMU or the event is not supported.
+            return;
+        };
+
+        let start = counter.read(&irq_guard);
+        for i in 0..1000 {
+            core::hint::black_box(i);
+        }
+        let end = counter.read(&irq_guard);
+        assert_ne!(start, end);
+    }
+}
@@ -0,0 +1,156 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The performance states (P-states) controlled by Enhanced Intel SpeedStep(R) Technology.
+//!
+//! Each P-state is identified by a ratio, which is the multiplier of the bus clock frequency. The
+//! software requests a P-state for the current CPU by writing the ratio to the `IA32_PERF_CTL`
+//! MSR, and the hardware reports the current P-state in the `IA32_PERF_STATUS` MSR. The range of
+//! the ratios is enumerated by the model-specific `MSR_PLATFORM_INFO` and `MSR_TURBO_RATIO_LIMIT`
+//! MSRs.
+//!
+//! Reference: Intel(R) 64 and IA-32 Architectures Software Developer's Manual, Section 15.1,
+//! Enhanced Intel SpeedStep(R) Technology, and Section 2.17, MSRs in the Intel(R) Microarchitecture
+//! Code Name Sandy Bridge.
+
+use spin::Once;
+use x86::msr::{rdmsr, wrmsr};
+
+use super::cpuid::cpuid;
+use crate::irq::DisabledLocalIrqGuard;
+
+const IA32_PERF_STATUS: u32 = 0x198;
+const IA32_PERF_CTL: u32 = 0x199;
+const IA32_MISC_ENABLE: u32 = 0x1a0;
+const MSR_PLATFORM_INFO: u32 = 0xce;
+const MSR_TURBO_RATIO_LIMIT: u32 = 0x1ad;
+
+/// The bit in `IA32_MISC_ENABLE` that indicates Enhanced Intel SpeedStep Technology is enabled.
+const MISC_ENABLE_EIST: u64 = 1 << 16;
+/// The bit in `CPUID.01H:ECX` that indicates Enhanced Intel SpeedStep Technology is supported.
+const CPUID_ECX_EIST: u32 = 1 << 7;
+/// The bit in `CPUID.01H:ECX` that indicates the CPU is running under a hypervisor.
+const CPUID_ECX_HYPERVISOR: u32 = 1 << 31;
+/// The bit in `CPUID.06H:EAX` that indicates Intel Turbo Boost Technology is available.
+const CPUID_EAX_TURBO: u32 = 1 << 1;
+
+/// The bits of the ratio in `IA32_PERF_CTL` and `IA32_PERF_STATUS`.
+const PERF_RATIO_SHIFT: u32 = 8;
+const PERF_RATIO_MASK: u64 = 0xff << PERF_RATIO_SHIFT;
+
+/// The frequency of the bus clock in kHz.
+///
+/// This is 100 MHz for all CPUs since the Intel(R) microarchitecture code name Sandy Bridge.
+pub const BUS_FREQ_KHZ: u32 = 100_000;
+
+/// The information about the P-states.
+#[derive(Debug)]
+pub struct PstateInfo {
+    min_ratio: u8,
+    max_ratio: u8,
+    turbo_ratio: u8,
+}
+
+impl PstateInfo {
+    /// Returns the ratio of the lowest P-state, i.e., the maximum efficiency ratio.
+    pub fn min_ratio(&self) -> u8 {
+        self.min_ratio
+    }
+
+    /// Returns the ratio of the highest non-turbo P-state, i.e., the base ratio.
+    pub fn max_ratio(&self) -> u8 {
+        self.max_ratio
+    }
+
+    /// Returns the ratio of the highest P-state when one core is active.
+    ///
+    /// The ratio is the same as [`Self::max_ratio`] if turbo is not available.
+    pub fn turbo_ratio(&self) -> u8 {
+        self.turbo_ratio
+    }
+}
+
+static PSTATE_INFO: Once<Option<PstateInfo>> = Once::new();
+
+/// Returns the information about the P-states.
+///
+/// This method will return `None` if the P-states cannot be controlled. Note that the P-states
+/// are not controlled under hypervisors, where the model-specific MSRs are usually not emulated.
+pub fn pstate_info() -> Option<&'static PstateInfo> {
+    PSTATE_INFO.call_once(probe_pstate_info).as_ref()
+}
+
+fn probe_pstate_info() -> Option<PstateInfo> {
+    // The model-specific MSRs are only available on Intel CPUs.
+    let vendor = cpuid(0, 0)?;
+    if (vendor.ebx, vendor.edx, vendor.ecx) != (0x756e_6547, 0x4965_6e69, 0x6c65_746e) {
+        return None;
+    }
+
+    let features = cpuid(1, 0)?;
+    if features.ecx & CPUID_ECX_EIST == 0 || features.ecx & CPUID_ECX_HYPERVISOR != 0 {
+        return None;
+    }
+
+    // SAFETY: The MSRs are available on Intel CPUs that support EIST. Reading them has no side
+    // effects.
+    let (misc_enable, platform_info) =
+        unsafe { (rdmsr(IA32_MISC_ENABLE), rdmsr(MSR_PLATFORM_INFO)) };
+    if misc_enable & MISC_ENABLE_EIST == 0 {
+        return None;
+    }
+
+    let max_ratio = (platform_info >> 8) as u8;
+    let min_ratio = (platform_info >> 40) as u8;
+    if min_ratio == 0 || max_ratio < min_ratio {
+        return None;
+    }
+
+    let has_turbo = cpuid(6, 0).is_some_and(|result| result.eax & CPUID_EAX_TURBO != 0);
+    let turbo_ratio = if has_turbo {
+        // SAFETY: The MSR is available if turbo is available. Reading it has no side effects.
+        let turbo_ratio_limit = unsafe { rdmsr(MSR_TURBO_RATIO_LIMIT) };
+        (turbo_ratio_limit as u8).max(max_ratio)
+    } else {
+        max_ratio
+    };
+
+    Some(PstateInfo {
+        min_ratio,
+        max_ratio,
+        turbo_ratio,
+    })
+}
+
+/// Requests the P-state with the ratio for the current CPU.
+///
+/// The ratio will be clamped to the range of the supported ratios.
+///
+/// # Panics
+///
+/// This method will panic if the P-states cannot be controlled.
+pub fn set_ratio(ratio: u8, _irq_guard: &DisabledLocalIrqGuard) {
+    let info = pstate_info().unwrap();
+    let ratio = ratio.clamp(info.min_ratio, info.turbo_ratio);
+
+    // SAFETY: Requesting a supported P-state only affects the performance of the current CPU.
+    unsafe {
+        let perf_ctl = rdmsr(IA32_PERF_CTL);
+        wrmsr(
+            IA32_PERF_CTL,
+            (perf_ctl & !PERF_RATIO_MASK) | ((ratio as u64) << PERF_RATIO_SHIFT),
+        );
+    }
+}
+
+/// Returns the ratio of the current P-state of the current CPU.
+///
+/// # Panics
+///
+/// This method will panic if the P-states cannot be controlled.
+pub fn current_ratio(_irq_guard: &DisabledLocalIrqGuard) -> u8 {
+    assert!(pstate_info().is_some());
+
+    // SAFETY: Reading the MSR has no side effects.
+    let perf_status = unsafe { rdmsr(IA32_PERF_STATUS) };
+    ((perf_status & PERF_RATIO_MASK) >> PERF_RATIO_SHIFT) as u8
+}
@@ -0,0 +1,115 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Cryptographic instructions of x86 processors.
+//!
+//! The kernel is compiled without SSE, so the instructions are issued by inline assembly.
+//! The XMM registers may hold the FPU state of user space when the kernel runs, so the
+//! registers used by the instructions are saved before and restored after each use.
+
+use core::arch::asm;
+
+use super::cpu::extension::{IsaExtensions, has_extensions};
+use crate::task::disable_preempt;
+
+/// The size of an AES block in bytes.
+const AES_BLOCK_SIZE: usize = 16;
+
+/// Returns whether the AES instructions (AES-NI) are available.
+pub fn has_aes() -> bool {
+    has_extensions(IsaExtensions::AES)
+}
+
+/// Encrypts a block in place with the AES instructions.
+///
+/// The round keys are the expanded key in the order defined by FIPS 197. There must be
+/// `Nr + 1` round keys, where `Nr` is the number of rounds.
+///
+/// # Panics
+///
+/// This function panics if the AES instructions are not available or if there are fewer
+/// than three round keys.
+pub fn aes_encrypt_block(round_keys: &[[u8; AES_BLOCK_SIZE]], block: &mut [u8; AES_BLOCK_SIZE]) {
+    assert!(has_aes());
+    assert!(round_keys.len() >= 3);
+
+    let mut saved = [0u8; 2 * AES_BLOCK_SIZE];
+    // Preemption must be disabled so that the XMM registers are not switched to those of
+    // other threads in the middle of the computation.
+    let _guard = disable_preempt();
+
+    // SAFETY: The AES instructions are available. The memory operands are valid for reads
+    // and writes of the given sizes. XMM0 and XMM1 are restored before the assembly ends.
+    unsafe {
+        asm!(
+            "movdqu [{saved}], xmm0",
+            "movdqu [{saved} + 16], xmm1",
+            "movdqu xmm0, [{block}]",
+            "movdqu xmm1, [{keys}]",
+            "pxor xmm0, xmm1",
+            "2:",
+            "add {keys}, 16",
+            "movdqu xmm1, [{keys}]",
+            "aesenc xmm0, xmm1",
+            "dec {rounds}",
+            "jnz 2b",
+            "movdqu xmm1, [{keys} + 16]",
+            "aesenclast xmm0, xmm1",
+            "movdqu [{block}], xmm0",
+            "movdqu xmm0, [{saved}]",
+            "movdqu xmm1, [{saved} + 16]",
+            saved = in(reg) saved.as_mut_ptr(),
+            block = in(reg) block.as_mut_ptr(),
+            keys = inout(reg) round_keys.as_ptr() => _,
+            rounds = inout(reg) round_keys.len() - 2 => _,
+            options(nostack)
+        );
+    }
+}
+
+/// Decrypts a block in place with the AES instructions.
+///
+/// The round keys are those of the equivalent inverse cipher defined by FIPS 197, i.e., the
+/// encryption round keys in the reverse order, with `InvMixColumns` applied to all but the
+/// first and the last ones. There must be `Nr + 1` round keys, where `Nr` is the number of
+/// rounds.
+///
+/// # Panics
+///
+/// This function panics if the AES instructions are not available or if there are fewer
+/// than three round keys.
+pub fn aes_decrypt_block(round_keys: &[[u8; AES_BLOCK_SIZE]], block: &mut [u8; AES_BLOCK_SIZE]) {
+    assert!(has_aes());
+    assert!(round_keys.len() >= 3);
+
+    let mut saved = [0u8; 2 * AES_BLOCK_SIZE];
+    // See `aes_encrypt_block` for why preemption is disabled.
+    let _guard = disable_preempt();
+
+    // SAFETY: The AES instructions are available. The memory operands are valid for reads
+    // and writes of the given sizes. XMM0 and XMM1 are restored before the assembly ends.
+    unsafe {
+        asm!(
+            "movdqu [{saved}], xmm0",
+            "movdqu [{saved} + 16], xmm1",
+            "movdqu xmm0, [{block}]",
+            "movdqu xmm1, [{keys}]",
+            "pxor xmm0, xmm1",
+            "2:",
+            "add {keys}, 16",
+            "movdqu xmm1, [{keys}]",
+            "aesdec xmm0, xmm1",
+            "dec {rounds}",
+            "jnz 2b",
+            "movdqu xmm1, [{keys} + 16]",
+            "aesdeclast xmm0, xmm1",
+            "movdqu [{block}], xmm0",
+            "movdqu xmm0, [{saved}]",
+            "movdqu xmm1, [{saved} + 16]",
+            saved = in(reg) saved.as_mut_ptr(),
+            block = in(reg) block.as_mut_ptr(),
+            keys = inout(reg) round_keys.as_ptr() => _,
+            rounds = inout(reg) round_keys.len() - 2 => _,
+            options(nostack)
+        );
+    }
+}
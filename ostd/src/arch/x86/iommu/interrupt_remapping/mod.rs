@@ -20,9 +20,12 @@ impl IrtEntryHandle {
         self.index
     }
 
-    pub fn enable(&self, vector: u32) {
-        self.table
-            .set_entry(self.index, table::IrtEntry::new_enabled(vector));
+    /// Enables the entry, which delivers the interrupts to the CPU with the (x2)APIC ID.
+    pub fn enable(&self, vector: u32, destination_id: u32) {
+        self.table.set_entry(
+            self.index,
+            table::IrtEntry::new_enabled(vector, destination_id),
+        );
 
         IOMMU_REGS
             .get()
@@ -1,5 +1,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use alloc::boxed::Box;
+
 use bit_field::BitField;
 use log::info;
 
@@ -137,6 +139,45 @@ impl IoApic {
         Ok(())
     }
 
+    /// Reads all the redirection entries.
+    pub(super) fn read_entries(&mut self) -> Box<[u64]> {
+        (0..=self.max_redirection_entry)
+            .map(|index| {
+                // SAFETY: `index` is inbound. The redirection table is safe to read.
+                let (low, high) = unsafe {
+                    (
+                        self.access.read(IoApicAccess::IOREDTBL + 2 * index),
+                        self.access.read(IoApicAccess::IOREDTBL + 2 * index + 1),
+                    )
+                };
+                (low as u64) | ((high as u64) << 32)
+            })
+            .collect()
+    }
+
+    /// Writes all the redirection entries that are previously read by [`Self::read_entries`].
+    ///
+    /// This is used to restore the redirection entries after the I/O APIC is reset.
+    pub(super) fn write_entries(&mut self, entries: &[u64]) {
+        assert_eq!(entries.len(), self.max_redirection_entry as usize + 1);
+
+        for (index, value) in (0..=self.max_redirection_entry).zip(entries.iter()) {
+            // SAFETY: `index` is inbound. The entries are read from the redirection table, so
+            // they are valid. The higher half is written first so that the entry is not enabled
+            // with a stale destination.
+            unsafe {
+                self.access.write(
+                    IoApicAccess::IOREDTBL + 2 * index + 1,
+                    value.get_bits(32..64) as u32,
+                );
+                self.access.write(
+                    IoApicAccess::IOREDTBL + 2 * index,
+                    value.get_bits(0..32) as u32,
+                );
+            }
+        }
+    }
+
     /// Returns the base number of the global system interrupts controlled by the I/O APIC.
     pub(super) fn interrupt_base(&self) -> u32 {
         self.interrupt_base
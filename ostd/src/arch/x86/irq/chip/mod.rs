@@ -30,6 +30,12 @@ mod pic;
 pub struct IrqChip {
     io_apics: SpinLock<Box<[IoApic]>>,
     overrides: Box<[IsaOverride]>,
+    has_pic: bool,
+}
+
+/// The state of an [`IrqChip`] that is saved before the system sleeps.
+pub(in crate::arch) struct IrqChipState {
+    io_apic_entries: Box<[Box<[u64]>]>,
 }
 
 struct IsaOverride {
@@ -105,6 +111,31 @@ impl IrqChip {
         self.map_gsi_pin_to(irq_line, gsi_index)
     }
 
+    /// Saves the state of the IRQ chip before the system enters a sleeping state.
+    pub(in crate::arch) fn save_state(&self) -> IrqChipState {
+        let mut io_apics = self.io_apics.lock();
+
+        let io_apic_entries = io_apics
+            .iter_mut()
+            .map(|io_apic| io_apic.read_entries())
+            .collect();
+        IrqChipState { io_apic_entries }
+    }
+
+    /// Restores the state of the IRQ chip after the system wakes up from a sleeping state.
+    ///
+    /// The IRQ chip may have been reset by the firmware, so its state is restored from scratch.
+    pub(in crate::arch) fn restore_state(&self, state: &IrqChipState) {
+        if self.has_pic {
+            pic::init_and_disable();
+        }
+
+        let mut io_apics = self.io_apics.lock();
+        for (io_apic, entries) in io_apics.iter_mut().zip(state.io_apic_entries.iter()) {
+            io_apic.write_entries(entries);
+        }
+    }
+
     /// Counts the number of I/O APICs.
     ///
     /// If I/O APICs are in use, this method counts how many I/O APICs are in use, otherwise, this
@@ -174,7 +205,8 @@ pub(in crate::arch) fn init(io_mem_builder: &IoMemAllocatorBuilder) {
     // "A one indicates that the system also has a PC-AT-compatible dual-8259 setup. The 8259
     // vectors must be disabled (that is, masked) when enabling the ACPI APIC operation"
     const PCAT_COMPAT: u32 = 1;
-    if madt_table.get().flags & PCAT_COMPAT != 0 {
+    let has_pic = madt_table.get().flags & PCAT_COMPAT != 0;
+    if has_pic {
         pic::init_and_disable();
     }
 
@@ -238,6 +270,7 @@ pub(in crate::arch) fn init(io_mem_builder: &IoMemAllocatorBuilder) {
     let irq_chip = IrqChip {
         io_apics: SpinLock::new(io_apics.into_boxed_slice()),
         overrides: isa_overrides.into_boxed_slice(),
+        has_pic,
     };
     IRQ_CHIP.call_once(|| irq_chip);
 }
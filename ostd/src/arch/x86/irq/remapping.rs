@@ -2,7 +2,12 @@
 
 use spin::once::Once;
 
-use crate::arch::iommu::{IrtEntryHandle, alloc_irt_entry, has_interrupt_remapping};
+use super::HwCpuId;
+use crate::{
+    arch::iommu::{IrtEntryHandle, alloc_irt_entry, has_interrupt_remapping},
+    cpu::CpuId,
+    smp::hw_cpu_id_of,
+};
 
 pub(crate) struct IrqRemapping {
     entry: Once<IrtEntryHandle>,
@@ -23,13 +28,23 @@ impl IrqRemapping {
         }
 
         self.entry.call_once(|| {
-            // Allocate and enable the IRT entry.
+            // Allocate and enable the IRT entry, which delivers the interrupts to the BSP.
             let handle = alloc_irt_entry().unwrap();
-            handle.enable(irq_num as u32);
+            handle.enable(irq_num as u32, bsp_destination_id());
             handle
         });
     }
 
+    /// Routes the remapped IRQ to the specific CPU.
+    ///
+    /// This will do nothing if interrupt remapping is disabled or not supported by the
+    /// architecture.
+    pub(crate) fn set_destination(&self, irq_num: u8, hw_cpu_id: HwCpuId) {
+        if let Some(handle) = self.entry.get() {
+            handle.enable(irq_num as u32, hw_cpu_id.as_u32());
+        }
+    }
+
     /// Gets the remapping index of the IRQ line.
     ///
     /// This method will return `None` if interrupt remapping is disabled or
@@ -38,3 +53,11 @@ impl IrqRemapping {
         Some(self.entry.get()?.index())
     }
 }
+
+/// Returns the APIC ID of the BSP.
+///
+/// Before the APs are booted, the APIC ID is unknown and the interrupts are delivered to the
+/// CPU whose APIC ID is zero, which is the BSP on all known platforms.
+fn bsp_destination_id() -> u32 {
+    hw_cpu_id_of(CpuId::bsp()).map_or(0, HwCpuId::as_u32)
+}
@@ -3,11 +3,12 @@
 pub(in crate::arch) mod dmar;
 pub(in crate::arch) mod remapping;
 
+use alloc::{boxed::Box, vec::Vec};
 use core::{num::NonZeroU8, ptr::NonNull};
 
 use acpi::{
-    AcpiHandler, AcpiTables,
-    address::AddressSpace,
+    AcpiHandler, AcpiTables, AmlTable,
+    address::{AddressSpace, GenericAddress},
     fadt::{Fadt, IaPcBootArchFlags},
     mcfg::Mcfg,
     rsdp::Rsdp,
@@ -102,6 +103,32 @@ pub struct AcpiInfo {
     pub reset_port_and_val: Option<(u16, u8)>,
     /// A memory region that is stolen for PCI configuration space.
     pub pci_ecam_region: Option<PciEcamRegion>,
+    /// The power management registers of the ACPI fixed hardware.
+    pub pm_registers: Option<AcpiPmRegisters>,
+}
+
+/// The power management registers of the ACPI fixed hardware.
+///
+/// Only the registers in the I/O space are supported.
+#[derive(Debug)]
+pub struct AcpiPmRegisters {
+    /// The I/O port of the PM1a event register block.
+    pub pm1a_event: u16,
+    /// The I/O port of the PM1b event register block, if any.
+    pub pm1b_event: Option<u16>,
+    /// The length of each PM1 event register block in bytes.
+    ///
+    /// The first half is the status register, and the second half is the enable register.
+    pub pm1_event_len: u8,
+    /// The I/O port of the PM1a control register block.
+    pub pm1a_control: u16,
+    /// The I/O port of the PM1b control register block, if any.
+    pub pm1b_control: Option<u16>,
+    /// The I/O port to which the value is written to transfer the ownership of the registers
+    /// from the firmware to the OS, if ACPI is not always enabled.
+    pub smi_command_and_acpi_enable: Option<(u16, u8)>,
+    /// The system control interrupt (SCI), which is an ISA interrupt on x86.
+    pub sci_interrupt: u8,
 }
 
 /// A memory region that is stolen for PCI configuration space.
@@ -118,16 +145,24 @@ pub struct PciEcamRegion {
 /// The [`AcpiInfo`] singleton.
 pub static ACPI_INFO: Once<AcpiInfo> = Once::new();
 
+/// The AML code in the DSDT and the SSDTs, which defines the ACPI namespace.
+///
+/// The code is copied from the tables, so it remains available even if the memory of the tables
+/// is reclaimed.
+pub static AML_TABLES: Once<Vec<Box<[u8]>>> = Once::new();
+
 pub(in crate::arch) fn init() {
     let mut acpi_info = AcpiInfo {
         century_register: None,
         boot_flags: None,
         reset_port_and_val: None,
         pci_ecam_region: None,
+        pm_registers: None,
     };
 
     let Some(acpi_tables) = get_acpi_tables() else {
         ACPI_INFO.call_once(|| acpi_info);
+        AML_TABLES.call_once(Vec::new);
         return;
     };
 
@@ -141,6 +176,7 @@ pub(in crate::arch) fn init() {
         {
             acpi_info.reset_port_and_val = Some((reset_port, fadt.reset_value));
         }
+        acpi_info.pm_registers = pm_registers_from(&fadt);
     };
 
     if let Ok(mcfg) = acpi_tables.find_table::<Mcfg>()
@@ -158,4 +194,99 @@ pub(in crate::arch) fn init() {
     log::info!("[ACPI]: Collected information {:?}", acpi_info);
 
     ACPI_INFO.call_once(|| acpi_info);
+
+    let aml_tables = acpi_tables
+        .dsdt()
+        .into_iter()
+        .chain(acpi_tables.ssdts())
+        .map(|table| copy_aml(&table))
+        .collect();
+    AML_TABLES.call_once(|| aml_tables);
+}
+
+/// Sets the firmware waking vector in the FACS.
+///
+/// When the system wakes up from a sleeping state, the firmware jumps to the waking vector in
+/// real mode, where CS is `vector >> 4` and IP is `vector & 0xF`.
+///
+/// This function fails if the FACS does not exist.
+///
+/// Reference: ACPI Specification, Version 6.5, Section 5.2.10 "Firmware ACPI Control Structure
+/// (FACS)".
+pub(in crate::arch) fn set_firmware_waking_vector(vector: u32) -> Result<(), ()> {
+    const LENGTH_OFFSET: usize = 4;
+    const FIRMWARE_WAKING_VECTOR_OFFSET: usize = 12;
+    const X_FIRMWARE_WAKING_VECTOR_OFFSET: usize = 24;
+
+    let fadt = get_acpi_tables()
+        .and_then(|acpi_tables| acpi_tables.find_table::<Fadt>().ok())
+        .ok_or(())?;
+    let facs_address = fadt.facs_address().map_err(|_| ())?;
+    if facs_address == 0 {
+        return Err(());
+    }
+    let facs_ptr = paddr_to_vaddr(facs_address) as *mut u8;
+
+    // SAFETY: The FACS is in the ACPI NVS memory, which is mapped in the linear mapping. The
+    // waking vectors are properly aligned in the FACS.
+    // FIXME: This only holds if we trust the hardware to provide a valid ACPI table. See the
+    // comments in `AcpiMemoryHandler::map_physical_region`.
+    unsafe {
+        let length = facs_ptr.add(LENGTH_OFFSET).cast::<u32>().read_volatile();
+        facs_ptr
+            .add(FIRMWARE_WAKING_VECTOR_OFFSET)
+            .cast::<u32>()
+            .write_volatile(vector);
+        // The 64-bit waking vector takes precedence over the 32-bit one if it is non-zero.
+        if length as usize >= X_FIRMWARE_WAKING_VECTOR_OFFSET + size_of::<u64>() {
+            facs_ptr
+                .add(X_FIRMWARE_WAKING_VECTOR_OFFSET)
+                .cast::<u64>()
+                .write_volatile(0);
+        }
+    }
+
+    Ok(())
+}
+
+fn pm_registers_from(fadt: &Fadt) -> Option<AcpiPmRegisters> {
+    fn io_port_of(address: &GenericAddress) -> Option<u16> {
+        if address.address_space != AddressSpace::SystemIo {
+            return None;
+        }
+        address.address.try_into().ok()
+    }
+
+    let pm1a_event_block = fadt.pm1a_event_block().ok()?;
+    let pm1b_event_block = fadt.pm1b_event_block().ok().flatten();
+    let pm1b_control_block = fadt.pm1b_control_block().ok().flatten();
+
+    // A zero SMI command port means that ACPI is always enabled.
+    let smi_command_and_acpi_enable = if fadt.smi_cmd_port != 0 && fadt.acpi_enable != 0 {
+        Some((fadt.smi_cmd_port.try_into().ok()?, fadt.acpi_enable))
+    } else {
+        None
+    };
+
+    Some(AcpiPmRegisters {
+        pm1a_event: io_port_of(&pm1a_event_block)?,
+        pm1b_event: pm1b_event_block.as_ref().and_then(io_port_of),
+        pm1_event_len: pm1a_event_block.bit_width / 8,
+        pm1a_control: io_port_of(&fadt.pm1a_control_block().ok()?)?,
+        pm1b_control: pm1b_control_block.as_ref().and_then(io_port_of),
+        smi_command_and_acpi_enable,
+        sci_interrupt: fadt.sci_interrupt.try_into().ok()?,
+    })
+}
+
+fn copy_aml(table: &AmlTable) -> Box<[u8]> {
+    let ptr = paddr_to_vaddr(table.address) as *const u8;
+
+    // SAFETY: The AML code is part of the ACPI table, which is mapped in the linear mapping and
+    // is valid for reads.
+    // FIXME: This only holds if we trust the hardware to provide a valid ACPI table. See the
+    // comments in `AcpiMemoryHandler::map_physical_region`.
+    let aml = unsafe { core::slice::from_raw_parts(ptr, table.length as usize) };
+
+    aml.into()
 }
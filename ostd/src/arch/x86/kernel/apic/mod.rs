@@ -75,7 +75,7 @@ pub fn get_or_init(_guard: &dyn PinCurrentCpu) -> &(dyn Apic + 'static) {
     // Initialize the APIC instance now.
     apic_instance.call_once(|| match APIC_TYPE.get().unwrap() {
         ApicType::XApic(io_mem) => {
-            let mut xapic = xapic::XApic::new(io_mem).unwrap();
+            let xapic = xapic::XApic::new(io_mem).unwrap();
             xapic.enable();
             let version = xapic.version();
             log::info!(
@@ -87,7 +87,7 @@ pub fn get_or_init(_guard: &dyn PinCurrentCpu) -> &(dyn Apic + 'static) {
             ForceSyncSend(Box::new(xapic))
         }
         ApicType::X2Apic => {
-            let mut x2apic = x2apic::X2Apic::new().unwrap();
+            let x2apic = x2apic::X2Apic::new().unwrap();
             x2apic.enable();
             let version = x2apic.version();
             log::info!(
@@ -108,6 +108,12 @@ pub fn get_or_init(_guard: &dyn PinCurrentCpu) -> &(dyn Apic + 'static) {
 }
 
 pub trait Apic: ApicTimer {
+    /// Enables the local APIC.
+    ///
+    /// The local APIC is enabled when it is initialized. It should be enabled again if the CPU
+    /// has been powered off, e.g., after the system wakes up from a sleeping state.
+    fn enable(&self);
+
     fn id(&self) -> u32;
 
     fn version(&self) -> u32;
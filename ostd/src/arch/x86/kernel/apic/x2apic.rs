@@ -32,8 +32,10 @@ impl X2Apic {
 
         has_extensions(IsaExtensions::X2APIC)
     }
+}
 
-    pub(super) fn enable(&mut self) {
+impl super::Apic for X2Apic {
+    fn enable(&self) {
         const X2APIC_ENABLE_BITS: u64 = {
             // IA32_APIC_BASE MSR's EN bit: xAPIC global enable/disable
             const EN_BIT_IDX: u8 = 11;
@@ -58,9 +60,7 @@ impl X2Apic {
             wrmsr(IA32_X2APIC_SIVR, svr);
         }
     }
-}
 
-impl super::Apic for X2Apic {
     fn id(&self) -> u32 {
         unsafe { rdmsr(IA32_X2APIC_APICID) as u32 }
     }
@@ -40,8 +40,10 @@ impl XApic {
 
         has_extensions(IsaExtensions::XAPIC)
     }
+}
 
-    pub(super) fn enable(&mut self) {
+impl super::Apic for XApic {
+    fn enable(&self) {
         const XAPIC_ENABLE_BITS: u64 = {
             // IA32_APIC_BASE MSR's EN bit: xAPIC global enable/disable
             const EN_BIT_IDX: u8 = 11;
@@ -63,9 +65,7 @@ impl XApic {
             self.io_mem.write_once(xapic::XAPIC_SVR as usize, &svr);
         }
     }
-}
 
-impl super::Apic for XApic {
     fn id(&self) -> u32 {
         unsafe { self.io_mem.read_once(xapic::XAPIC_ID as usize) }
     }
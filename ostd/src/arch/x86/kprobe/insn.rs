@@ -0,0 +1,443 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! A minimal decoder of x86-64 instructions.
+//!
+//! The decoder does not fully decode an instruction. It only finds out what kprobes need to
+//! single-step the instruction out of line: the length of the instruction, the position of its
+//! RIP-relative displacement (or relative branch target), and whether it is a call.
+//!
+//! Instructions that cannot be single-stepped out of line safely are rejected. These include
+//! the instructions that change the flags that the single-step relies on (e.g., `pushf`,
+//! `popf`, `cli`, and `sti`), the instructions that trap (e.g., `int3` and `syscall`), the
+//! branches with 8-bit displacements (which cannot reach the original targets from the
+//! instruction slots), the repeated string instructions (which are single-stepped one
+//! iteration at a time), and the VEX- or EVEX-encoded instructions.
+
+use crate::mm::Vaddr;
+
+/// The maximum length of an x86-64 instruction.
+pub(super) const MAX_INSN_LEN: usize = 15;
+
+/// A decoded instruction.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Insn {
+    bytes: [u8; MAX_INSN_LEN],
+    len: usize,
+    /// The offset of the 32-bit displacement relative to the next instruction, if any.
+    ///
+    /// The displacement is either the displacement of a RIP-relative memory operand or the
+    /// target of a relative branch.
+    rel32_offset: Option<usize>,
+    is_call: bool,
+}
+
+impl Insn {
+    /// Decodes the instruction at the beginning of `bytes`.
+    ///
+    /// This method returns `None` if the instruction is invalid or cannot be single-stepped
+    /// out of line.
+    pub(super) fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut decoder = Decoder {
+            bytes,
+            pos: 0,
+            operand_size: false,
+            address_size: false,
+            rep: false,
+            rex_w: false,
+        };
+        let format = decoder.decode()?;
+
+        let mut insn_bytes = [0; MAX_INSN_LEN];
+        insn_bytes[..decoder.pos].copy_from_slice(&bytes[..decoder.pos]);
+        Some(Self {
+            bytes: insn_bytes,
+            len: decoder.pos,
+            rel32_offset: format.rel32_offset,
+            is_call: format.is_call,
+        })
+    }
+
+    /// Returns the length of the instruction.
+    pub(super) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the bytes of the instruction.
+    pub(super) fn bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    /// Returns whether the instruction is a call, which pushes a return address.
+    pub(super) fn is_call(&self) -> bool {
+        self.is_call
+    }
+
+    /// Returns the bytes of the instruction that behaves the same at `new_addr` as the
+    /// instruction at `addr`.
+    ///
+    /// This method returns `None` if the relative displacement cannot be adjusted to fit in
+    /// 32 bits.
+    pub(super) fn relocate(&self, addr: Vaddr, new_addr: Vaddr) -> Option<[u8; MAX_INSN_LEN]> {
+        let mut bytes = self.bytes;
+
+        if let Some(offset) = self.rel32_offset {
+            let disp_bytes = &mut bytes[offset..offset + 4];
+            let disp = i32::from_le_bytes(disp_bytes.try_into().unwrap());
+            let delta = (addr as i64).wrapping_sub(new_addr as i64);
+            let new_disp = i32::try_from(disp as i64 + delta).ok()?;
+            disp_bytes.copy_from_slice(&new_disp.to_le_bytes());
+        }
+
+        Some(bytes)
+    }
+}
+
+/// The operands that an opcode expects.
+#[derive(Debug, Clone, Copy)]
+struct Format {
+    has_modrm: bool,
+    imm_len: usize,
+    /// Whether the immediate is a 32-bit relative branch target.
+    is_rel32: bool,
+    is_call: bool,
+    /// The offset of the 32-bit RIP-relative displacement, which is found when decoding the
+    /// ModRM byte.
+    rel32_offset: Option<usize>,
+}
+
+impl Format {
+    const NONE: Self = Self {
+        has_modrm: false,
+        imm_len: 0,
+        is_rel32: false,
+        is_call: false,
+        rel32_offset: None,
+    };
+
+    const MODRM: Self = Self {
+        has_modrm: true,
+        ..Self::NONE
+    };
+
+    const fn imm(imm_len: usize) -> Self {
+        Self {
+            imm_len,
+            ..Self::NONE
+        }
+    }
+
+    const fn modrm_imm(imm_len: usize) -> Self {
+        Self {
+            has_modrm: true,
+            imm_len,
+            ..Self::NONE
+        }
+    }
+
+    const fn rel32(is_call: bool) -> Self {
+        Self {
+            imm_len: 4,
+            is_rel32: true,
+            is_call,
+            ..Self::NONE
+        }
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    /// Whether the operand-size override prefix (`0x66`) is present.
+    operand_size: bool,
+    /// Whether the address-size override prefix (`0x67`) is present.
+    address_size: bool,
+    /// Whether the `rep`/`repne` prefix (`0xf3`/`0xf2`) is present.
+    rep: bool,
+    /// Whether the `W` bit of the REX prefix is set.
+    rex_w: bool,
+}
+
+impl Decoder<'_> {
+    fn decode(&mut self) -> Option<Format> {
+        self.decode_prefixes()?;
+
+        let opcode = self.next()?;
+        let mut format = if opcode == 0x0f {
+            self.decode_two_byte_opcode()?
+        } else {
+            self.one_byte_opcode_format(opcode)?
+        };
+
+        if format.has_modrm {
+            let modrm = self.decode_modrm(&mut format)?;
+            if opcode != 0x0f {
+                self.check_one_byte_opcode_group(opcode, modrm, &mut format)?;
+            }
+        }
+
+        if format.is_rel32 {
+            format.rel32_offset = Some(self.pos);
+        }
+        self.pos += format.imm_len;
+
+        (self.pos <= self.bytes.len().min(MAX_INSN_LEN)).then_some(format)
+    }
+
+    fn decode_prefixes(&mut self) -> Option<()> {
+        loop {
+            match self.peek()? {
+                0x66 => self.operand_size = true,
+                0x67 => self.address_size = true,
+                0xf2 | 0xf3 => self.rep = true,
+                // The `lock` prefix and the segment override prefixes.
+                0xf0 | 0x26 | 0x2e | 0x36 | 0x3e | 0x64 | 0x65 => (),
+                _ => break,
+            }
+            self.pos += 1;
+        }
+
+        // The REX prefix must immediately precede the opcode.
+        let rex = self.peek()?;
+        if rex & 0xf0 == 0x40 {
+            self.rex_w = rex & 0x08 != 0;
+            self.pos += 1;
+        }
+
+        Some(())
+    }
+
+    fn one_byte_opcode_format(&self, opcode: u8) -> Option<Format> {
+        let imm_z = if self.operand_size { 2 } else { 4 };
+
+        let format = match opcode {
+            // The arithmetic and logical instructions (e.g., `add` and `cmp`).
+            0x00..=0x3f if opcode & 0x07 < 0x04 => Format::MODRM,
+            0x00..=0x3f if opcode & 0x07 == 0x04 => Format::imm(1),
+            0x00..=0x3f if opcode & 0x07 == 0x05 => Format::imm(imm_z),
+            // `push` and `pop`.
+            0x50..=0x5f => Format::NONE,
+            // `movsxd`.
+            0x63 => Format::MODRM,
+            0x68 => Format::imm(imm_z),
+            0x69 => Format::modrm_imm(imm_z),
+            0x6a => Format::imm(1),
+            0x6b => Format::modrm_imm(1),
+            // `ins` and `outs`.
+            0x6c..=0x6f if !self.rep => Format::NONE,
+            0x80 | 0x83 | 0xc0 | 0xc1 | 0xc6 => Format::modrm_imm(1),
+            0x81 | 0xc7 => Format::modrm_imm(imm_z),
+            // `test`, `xchg`, `mov`, `lea`, and `pop`. Moving to segment registers (`0x8e`) is
+            // excluded.
+            0x84..=0x8d | 0x8f => Format::MODRM,
+            // `xchg`, `nop`, `cbw`, `cwd`, `fwait`, `sahf`, and `lahf`.
+            0x90..=0x99 | 0x9b | 0x9e | 0x9f => Format::NONE,
+            // `mov` with a 64-bit (or 32-bit) memory offset.
+            0xa0..=0xa3 => Format::imm(if self.address_size { 4 } else { 8 }),
+            // The string instructions.
+            0xa4..=0xa7 | 0xaa..=0xaf if !self.rep => Format::NONE,
+            0xa8 => Format::imm(1),
+            0xa9 => Format::imm(imm_z),
+            0xb0..=0xb7 => Format::imm(1),
+            0xb8..=0xbf => Format::imm(if self.rex_w { 8 } else { imm_z }),
+            // `ret`, `enter`, and `leave`.
+            0xc2 => Format::imm(2),
+            0xc3 | 0xc9 => Format::NONE,
+            0xc8 => Format::imm(3),
+            // The shift and x87 instructions.
+            0xd0..=0xd3 | 0xd8..=0xdf => Format::MODRM,
+            // `xlat`.
+            0xd7 => Format::NONE,
+            // `in` and `out`.
+            0xe4..=0xe7 => Format::imm(1),
+            0xec..=0xef => Format::NONE,
+            // `call` and `jmp` with 32-bit displacements.
+            0xe8 if !self.operand_size => Format::rel32(true),
+            0xe9 if !self.operand_size => Format::rel32(false),
+            // `cmc`, `clc`, `stc`, `cld`, and `std`.
+            0xf5 | 0xf8 | 0xf9 | 0xfc | 0xfd => Format::NONE,
+            // The groups whose operands depend on the ModRM byte.
+            0xf6 | 0xf7 | 0xfe | 0xff => Format::MODRM,
+            _ => return None,
+        };
+
+        Some(format)
+    }
+
+    fn decode_two_byte_opcode(&mut self) -> Option<Format> {
+        let opcode = self.next()?;
+
+        let format = match opcode {
+            // The system instructions (e.g., `lgdt` and `swapgs`), `syscall`, `sysret`,
+            // `sysenter`, `sysexit`, `rsm`, the undefined instructions (e.g., `ud2`), and the
+            // reserved opcodes.
+            0x00 | 0x01 | 0x04 | 0x05 | 0x07 | 0x0a..=0x0c | 0x0f | 0x24..=0x27 => return None,
+            0x34..=0x36 | 0x39 | 0x3b..=0x3f | 0x7a | 0x7b | 0xaa | 0xb9 | 0xff => return None,
+            // `clts`, `invd`, `wbinvd`, `femms`, `wrmsr`, `rdtsc`, `rdmsr`, `rdpmc`, `getsec`,
+            // `emms`, `push fs`, `pop fs`, `cpuid`, `push gs`, `pop gs`, and `bswap`.
+            0x06 | 0x08 | 0x09 | 0x0e | 0x30..=0x33 | 0x37 | 0x77 => Format::NONE,
+            0xa0..=0xa2 | 0xa8 | 0xa9 | 0xc8..=0xcf => Format::NONE,
+            // The three-byte opcodes.
+            0x38 => {
+                self.next()?;
+                Format::MODRM
+            }
+            0x3a => {
+                self.next()?;
+                Format::modrm_imm(1)
+            }
+            0x70..=0x73 | 0xa4 | 0xac | 0xba | 0xc2 | 0xc4..=0xc6 => Format::modrm_imm(1),
+            // `jcc` with 32-bit displacements.
+            0x80..=0x8f if !self.operand_size => Format::rel32(false),
+            0x80..=0x8f => return None,
+            _ => Format::MODRM,
+        };
+
+        Some(format)
+    }
+
+    /// Decodes the ModRM byte and the SIB byte and the displacement that follow it.
+    ///
+    /// This method returns the ModRM byte.
+    fn decode_modrm(&mut self, format: &mut Format) -> Option<u8> {
+        let modrm = self.next()?;
+        let mode = modrm >> 6;
+        let rm = modrm & 0x07;
+
+        let disp_len = match mode {
+            0b00 if rm == 0b100 => {
+                let sib = self.next()?;
+                if sib & 0x07 == 0b101 { 4 } else { 0 }
+            }
+            0b00 if rm == 0b101 => {
+                // In 64-bit mode, this encodes a RIP-relative memory operand.
+                format.rel32_offset = Some(self.pos);
+                4
+            }
+            0b00 => 0,
+            0b01 | 0b10 => {
+                if rm == 0b100 {
+                    self.next()?;
+                }
+                if mode == 0b01 { 1 } else { 4 }
+            }
+            _ => 0,
+        };
+        self.pos += disp_len;
+
+        Some(modrm)
+    }
+
+    /// Checks the one-byte opcodes whose operands or validity depend on the `reg` field of the
+    /// ModRM byte.
+    fn check_one_byte_opcode_group(
+        &self,
+        opcode: u8,
+        modrm: u8,
+        format: &mut Format,
+    ) -> Option<()> {
+        let reg = (modrm >> 3) & 0x07;
+        let imm_z = if self.operand_size { 2 } else { 4 };
+
+        match (opcode, reg) {
+            // `pop` and `mov`. The other encodings are invalid or transactional (e.g., `xbegin`).
+            (0x8f | 0xc6 | 0xc7, 0) => (),
+            (0x8f | 0xc6 | 0xc7, _) => return None,
+            // `test` with an immediate.
+            (0xf6, 0 | 1) => format.imm_len = 1,
+            (0xf7, 0 | 1) => format.imm_len = imm_z,
+            // `inc` and `dec`.
+            (0xfe, 0 | 1) => (),
+            (0xfe, _) => return None,
+            // The indirect call.
+            (0xff, 2) => format.is_call = true,
+            // The far call, the far jump, and the invalid encoding.
+            (0xff, 3 | 5 | 7) => return None,
+            _ => (),
+        }
+
+        Some(())
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let byte = self.peek()?;
+        self.pos += 1;
+        Some(byte)
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use super::*;
+    use crate::prelude::*;
+
+    fn decode_len(bytes: &[u8]) -> Option<usize> {
+        Insn::decode(bytes).map(|insn| insn.len())
+    }
+
+    #[ktest]
+    fn decode_common_instructions() {
+        // push rbp
+        assert_eq!(decode_len(&[0x55]), Some(1));
+        // mov rbp, rsp
+        assert_eq!(decode_len(&[0x48, 0x89, 0xe5]), Some(3));
+        // sub rsp, 0x20
+        assert_eq!(decode_len(&[0x48, 0x83, 0xec, 0x20]), Some(4));
+        // mov eax, dword ptr [rsp + 8]
+        assert_eq!(decode_len(&[0x8b, 0x44, 0x24, 0x08]), Some(4));
+        // mov rax, 0x1122334455667788
+        let movabs = [0x48, 0xb8, 0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11];
+        assert_eq!(decode_len(&movabs), Some(10));
+        // test byte ptr [rdi], 1
+        assert_eq!(decode_len(&[0xf6, 0x07, 0x01]), Some(3));
+        // endbr64
+        assert_eq!(decode_len(&[0xf3, 0x0f, 0x1e, 0xfa]), Some(4));
+        // pshufd xmm0, xmm1, 0
+        assert_eq!(decode_len(&[0x66, 0x0f, 0x70, 0xc1, 0x00]), Some(5));
+        // ret
+        assert_eq!(decode_len(&[0xc3]), Some(1));
+    }
+
+    #[ktest]
+    fn reject_unsupported_instructions() {
+        // int3
+        assert_eq!(decode_len(&[0xcc]), None);
+        // jne +5
+        assert_eq!(decode_len(&[0x75, 0x05]), None);
+        // pushf
+        assert_eq!(decode_len(&[0x9c]), None);
+        // cli
+        assert_eq!(decode_len(&[0xfa]), None);
+        // syscall
+        assert_eq!(decode_len(&[0x0f, 0x05]), None);
+        // rep movsb
+        assert_eq!(decode_len(&[0xf3, 0xa4]), None);
+        // vzeroupper
+        assert_eq!(decode_len(&[0xc5, 0xf8, 0x77]), None);
+        // A truncated instruction.
+        assert_eq!(decode_len(&[0x48, 0x83, 0xec]), None);
+    }
+
+    #[ktest]
+    fn relocate_relative_instructions() {
+        // lea rax, [rip + 0x10]
+        let insn = Insn::decode(&[0x48, 0x8d, 0x05, 0x10, 0x00, 0x00, 0x00]).unwrap();
+        assert_eq!(insn.len(), 7);
+        assert!(!insn.is_call());
+        let bytes = insn.relocate(0x2000, 0x1000).unwrap();
+        assert_eq!(&bytes[..7], &[0x48, 0x8d, 0x05, 0x10, 0x10, 0x00, 0x00]);
+
+        // call +0x100
+        let insn = Insn::decode(&[0xe8, 0x00, 0x01, 0x00, 0x00]).unwrap();
+        assert_eq!(insn.len(), 5);
+        assert!(insn.is_call());
+        let bytes = insn.relocate(0x1000, 0x1100).unwrap();
+        assert_eq!(&bytes[..5], &[0xe8, 0x00, 0x00, 0x00, 0x00]);
+
+        // The new displacement does not fit in 32 bits.
+        assert!(insn.relocate(0x1_0000_0000, 0x1000).is_none());
+    }
+}
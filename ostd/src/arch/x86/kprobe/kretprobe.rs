@@ -0,0 +1,151 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Kernel return probes (kretprobes).
+
+use alloc::collections::BTreeMap;
+use core::{
+    fmt::Debug,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use super::{__kretprobe_trampoline, Kprobe, KprobeHandler, call_handler, trapped_stack_pointer};
+use crate::{
+    arch::trap::TrapFrame,
+    prelude::*,
+    sync::{LocalIrqDisabled, SpinLock},
+};
+
+/// A kernel return probe (kretprobe), which calls the handler when a function returns.
+///
+/// When the kretprobe is dropped, it will be unregistered automatically. The function calls
+/// that have been probed will still return correctly, but their returns will not be handled.
+#[must_use]
+pub struct Kretprobe {
+    inner: Arc<KretprobeInner>,
+    kprobe: Kprobe,
+}
+
+struct KretprobeInner {
+    entry_handler: Option<Box<KprobeHandler>>,
+    return_handler: Box<KprobeHandler>,
+    is_registered: AtomicBool,
+    nr_missed: AtomicUsize,
+}
+
+impl Kretprobe {
+    /// Registers a kretprobe at the function at `addr`.
+    ///
+    /// The entry handler is called when the function is entered, and the return handler is
+    /// called when the function returns. When the return handler is called, the RAX register
+    /// holds the return value and the RIP register holds the return address.
+    ///
+    /// See [`Kprobe::register`] for the errors.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `addr` is the entry of a function in the kernel text, which
+    /// is not part of the kprobe machinery. The function must return with `ret`.
+    pub unsafe fn register(
+        addr: Vaddr,
+        entry_handler: Option<Box<KprobeHandler>>,
+        return_handler: Box<KprobeHandler>,
+    ) -> Result<Self> {
+        let inner = Arc::new(KretprobeInner {
+            entry_handler,
+            return_handler,
+            is_registered: AtomicBool::new(true),
+            nr_missed: AtomicUsize::new(0),
+        });
+
+        let inner_cloned = inner.clone();
+        let pre_handler = move |f: &mut TrapFrame| inner_cloned.on_entry(f);
+        // SAFETY: The safety requirements are upheld by the caller.
+        let kprobe = unsafe { Kprobe::register(addr, Some(Box::new(pre_handler)), None) }?;
+
+        Ok(Self { inner, kprobe })
+    }
+
+    /// Returns the address of the probed function.
+    pub fn addr(&self) -> Vaddr {
+        self.kprobe.addr()
+    }
+
+    /// Returns the number of the function calls whose handlers are skipped.
+    pub fn nr_missed(&self) -> usize {
+        self.kprobe.nr_missed() + self.inner.nr_missed.load(Ordering::Relaxed)
+    }
+}
+
+impl Debug for Kretprobe {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Kretprobe")
+            .field("kprobe", &self.kprobe)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for Kretprobe {
+    fn drop(&mut self) {
+        self.inner.is_registered.store(false, Ordering::Relaxed);
+    }
+}
+
+impl KretprobeInner {
+    fn on_entry(self: &Arc<Self>, f: &mut TrapFrame) {
+        if let Some(entry_handler) = self.entry_handler.as_ref() {
+            entry_handler(f);
+        }
+
+        // At the entry of the function, the stack pointer points to the return address.
+        let ret_addr_ptr = trapped_stack_pointer(f) as *mut usize;
+        // SAFETY: The caller of `Kretprobe::register` guarantees that the kprobe is at the
+        // entry of a function.
+        let ret_addr = unsafe { core::ptr::read(ret_addr_ptr) };
+
+        let instance = KretprobeInstance {
+            kretprobe: self.clone(),
+            ret_addr,
+        };
+        INSTANCES.lock().insert(ret_addr_ptr.addr(), instance);
+
+        let trampoline = __kretprobe_trampoline as *const () as usize;
+        // SAFETY: The return address is replaced with the trampoline, which will jump back to
+        // the original return address.
+        unsafe { core::ptr::write(ret_addr_ptr, trampoline) };
+    }
+}
+
+/// A function call whose return is being probed.
+struct KretprobeInstance {
+    kretprobe: Arc<KretprobeInner>,
+    /// The original return address.
+    ret_addr: Vaddr,
+}
+
+/// The function calls whose returns are being probed, keyed by the addresses of their return
+/// addresses on the stacks.
+///
+/// If a function call never returns (e.g., the task exits), its instance is replaced when the
+/// stack is reused by another probed function call.
+static INSTANCES: SpinLock<BTreeMap<Vaddr, KretprobeInstance>, LocalIrqDisabled> =
+    SpinLock::new(BTreeMap::new());
+
+/// Handles the breakpoint exception raised by the trampoline.
+pub(super) fn handle_return(f: &mut TrapFrame) {
+    // The `ret` instruction has popped the return address.
+    let ret_addr_ptr = trapped_stack_pointer(f) - size_of::<usize>();
+    let instance = INSTANCES
+        .lock()
+        .remove(&ret_addr_ptr)
+        .expect("the kretprobe instance of the returning function call is missing");
+
+    f.rip = instance.ret_addr;
+
+    let kretprobe = &instance.kretprobe;
+    if !kretprobe.is_registered.load(Ordering::Relaxed) {
+        return;
+    }
+    if !call_handler(&kretprobe.return_handler, f) {
+        kretprobe.nr_missed.fetch_add(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,469 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Kernel probes (kprobes).
+//!
+//! A [`Kprobe`] instruments an instruction of the kernel at runtime. Registering a kprobe
+//! replaces the first byte of the probed instruction with `int3`. When the breakpoint is hit,
+//! the pre-handler of the kprobe is called. Then the probed instruction is single-stepped out of
+//! line, i.e., a copy of the instruction is executed in an instruction slot with the trap flag
+//! set. After the single-step, the post-handler is called and the execution resumes after the
+//! probed instruction.
+//!
+//! A [`Kretprobe`] is built on a kprobe at the entry of a function. It replaces the return
+//! address of the function with a trampoline, so its handler is called when the function
+//! returns.
+//!
+//! If the copy of the probed instruction raises an exception (e.g., a page fault when accessing
+//! the user space), the exception is handled as if it were raised by the probed instruction.
+//! So the exception table works as usual, and the kprobe fires again when the probed instruction
+//! is re-executed after the exception is handled.
+//!
+//! Like Linux, the handlers are called in the trap context with the local IRQs disabled. If a
+//! kprobe is hit while a handler is running on the same CPU, the handlers of the kprobe are
+//! skipped and the hit is counted as missed.
+//!
+//! The current implementation has the following limitations:
+//!  - The probed instruction is modified without synchronizing the instruction streams of the
+//!    other CPUs. This is fine on the CPUs that Asterinas supports today because only one byte
+//!    is modified, but is not guaranteed by the architecture.
+//!  - Some instructions cannot be probed. See the [`insn`] module for details.
+//!  - The kprobe machinery itself (e.g., the heap allocator and the spin locks) must not be
+//!    probed. Otherwise, the CPU may deadlock.
+
+mod insn;
+mod kretprobe;
+
+use alloc::collections::BTreeMap;
+use core::{
+    fmt::Debug,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+};
+
+use x86_64::registers::rflags::RFlags;
+
+use self::insn::{Insn, MAX_INSN_LEN};
+pub use self::kretprobe::Kretprobe;
+use crate::{
+    Error,
+    arch::trap::TrapFrame,
+    cpu_local_cell,
+    prelude::*,
+    sync::{LocalIrqDisabled, SpinLock},
+};
+
+core::arch::global_asm!(
+    include_str!("kprobe.S"),
+    INSN_SLOTS_SIZE = const NR_INSN_SLOTS * INSN_SLOT_SIZE,
+);
+
+unsafe extern "C" {
+    fn __kprobe_insn_slots();
+    fn __kretprobe_trampoline();
+}
+
+/// A type alias for the handler functions of kprobes.
+///
+/// The handlers can inspect and modify the trap frame, which contains the registers when the
+/// probe is hit.
+pub type KprobeHandler = dyn Fn(&mut TrapFrame) + Sync + Send + 'static;
+
+/// A kernel probe (kprobe), which calls the handlers when an instruction is executed.
+///
+/// When the kprobe is dropped, it will be unregistered automatically.
+#[must_use]
+pub struct Kprobe {
+    probe: Arc<ProbePoint>,
+}
+
+impl Kprobe {
+    /// Registers a kprobe at the instruction at `addr`.
+    ///
+    /// The pre-handler is called before the instruction is executed. If the pre-handler changes
+    /// the RIP register, the instruction is skipped and the execution resumes at the new RIP.
+    /// Otherwise, the instruction is executed and then the post-handler is called.
+    ///
+    /// This method fails with [`Error::InvalidArgs`] if the instruction cannot be probed, and
+    /// with [`Error::NotEnoughResources`] if the instruction has been probed or there are no
+    /// free instruction slots.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `addr` is the start of an instruction in the kernel text, and
+    /// that the instruction is not part of the kprobe machinery.
+    pub unsafe fn register(
+        addr: Vaddr,
+        pre_handler: Option<Box<KprobeHandler>>,
+        post_handler: Option<Box<KprobeHandler>>,
+    ) -> Result<Self> {
+        let slots_start = __kprobe_insn_slots as *const () as usize;
+        let trampoline = __kretprobe_trampoline as *const () as usize;
+        if (slots_start..=trampoline).contains(&addr) {
+            return Err(Error::InvalidArgs);
+        }
+
+        let mut bytes = [0; MAX_INSN_LEN];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            // SAFETY: The caller guarantees that `addr` is in the kernel text. Reading past the
+            // end of the instruction is fine because the kernel text is mapped.
+            *byte = unsafe { core::ptr::read_volatile((addr + i) as *const u8) };
+        }
+        let insn = Insn::decode(&bytes).ok_or(Error::InvalidArgs)?;
+
+        let slot = InsnSlot::alloc().ok_or(Error::NotEnoughResources)?;
+        let relocated = insn.relocate(addr, slot.addr()).ok_or(Error::InvalidArgs)?;
+        // SAFETY: The slot is allocated and no CPU is executing it.
+        unsafe { write_text(slot.addr(), &relocated[..insn.len()]) };
+
+        let probe = Arc::new(ProbePoint {
+            addr,
+            insn,
+            slot,
+            pre_handler,
+            post_handler,
+            nr_missed: AtomicUsize::new(0),
+        });
+
+        let mut probe_points = PROBE_POINTS.lock();
+        if probe_points.contains_key(&addr) {
+            return Err(Error::NotEnoughResources);
+        }
+        probe_points.insert(addr, probe.clone());
+        // SAFETY: The caller guarantees that `addr` is the start of an instruction. Replacing
+        // its first byte with `int3` is atomic.
+        unsafe { write_text(addr, &[INT3]) };
+
+        Ok(Self { probe })
+    }
+
+    /// Returns the address of the probed instruction.
+    pub fn addr(&self) -> Vaddr {
+        self.probe.addr
+    }
+
+    /// Returns the number of the hits whose handlers are skipped.
+    pub fn nr_missed(&self) -> usize {
+        self.probe.nr_missed.load(Ordering::Relaxed)
+    }
+}
+
+impl Debug for Kprobe {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Kprobe")
+            .field("addr", &self.probe.addr)
+            .field("insn", &self.probe.insn)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for Kprobe {
+    fn drop(&mut self) {
+        let mut probe_points = PROBE_POINTS.lock();
+        // SAFETY: The first byte of the probed instruction is restored, which is atomic. The CPUs
+        // that have hit the breakpoint will find it restored and re-execute the instruction.
+        unsafe { write_text(self.probe.addr, &self.probe.insn.bytes()[..1]) };
+        probe_points.remove(&self.probe.addr);
+    }
+}
+
+/// A probed instruction.
+struct ProbePoint {
+    addr: Vaddr,
+    insn: Insn,
+    /// The slot where the instruction is single-stepped.
+    slot: InsnSlot,
+    pre_handler: Option<Box<KprobeHandler>>,
+    post_handler: Option<Box<KprobeHandler>>,
+    nr_missed: AtomicUsize,
+}
+
+/// The registered probe points, keyed by the addresses of the probed instructions.
+static PROBE_POINTS: SpinLock<BTreeMap<Vaddr, Arc<ProbePoint>>, LocalIrqDisabled> =
+    SpinLock::new(BTreeMap::new());
+
+/// The opcode of `int3`.
+const INT3: u8 = 0xcc;
+
+const TRAP_FLAG: usize = RFlags::TRAP_FLAG.bits() as usize;
+const INTERRUPT_FLAG: usize = RFlags::INTERRUPT_FLAG.bits() as usize;
+
+/// The number of the instruction slots, which is also the maximum number of the kprobes.
+const NR_INSN_SLOTS: usize = 64;
+/// The size of an instruction slot.
+const INSN_SLOT_SIZE: usize = 16;
+
+/// The bitmap of the allocated instruction slots.
+static ALLOCATED_SLOTS: AtomicU64 = AtomicU64::new(0);
+
+/// An instruction slot, which is freed when dropped.
+#[derive(Debug)]
+struct InsnSlot {
+    index: usize,
+}
+
+impl InsnSlot {
+    fn alloc() -> Option<Self> {
+        let allocated = ALLOCATED_SLOTS
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |allocated| {
+                let index = allocated.trailing_ones() as usize;
+                if index >= NR_INSN_SLOTS {
+                    return None;
+                }
+                Some(allocated | (1 << index))
+            })
+            .ok()?;
+
+        Some(Self {
+            index: allocated.trailing_ones() as usize,
+        })
+    }
+
+    fn addr(&self) -> Vaddr {
+        __kprobe_insn_slots as *const () as usize + self.index * INSN_SLOT_SIZE
+    }
+}
+
+impl Drop for InsnSlot {
+    fn drop(&mut self) {
+        ALLOCATED_SLOTS.fetch_and(!(1 << self.index), Ordering::Release);
+    }
+}
+
+/// Writes `bytes` to the kernel text at `addr`.
+///
+/// # Safety
+///
+/// The caller must ensure that the bytes are written to the kernel text, and that the
+/// instructions that other CPUs may be executing stay valid.
+unsafe fn write_text(addr: Vaddr, bytes: &[u8]) {
+    for (i, byte) in bytes.iter().enumerate() {
+        // SAFETY: The kernel text is mapped as writable. The caller guarantees that the write
+        // does not break other CPUs.
+        unsafe { core::ptr::write_volatile((addr + i) as *mut u8, *byte) };
+    }
+}
+
+cpu_local_cell! {
+    /// The probe point whose instruction is being single-stepped on this CPU.
+    ///
+    /// The pointer is obtained from [`Arc::into_raw`], or is null if there is none.
+    static STEPPING_PROBE: *const ProbePoint = core::ptr::null();
+    /// Whether the local IRQs were enabled before the single-step started.
+    static WAS_STEPPING_IRQ_ENABLED: bool = false;
+    /// Whether a kprobe handler is running on this CPU.
+    static IN_HANDLER: bool = false;
+}
+
+/// Calls the kprobe handler if no handlers are running on this CPU.
+///
+/// This function returns `false` if the handler is skipped.
+fn call_handler(handler: &KprobeHandler, f: &mut TrapFrame) -> bool {
+    if IN_HANDLER.load() {
+        return false;
+    }
+
+    IN_HANDLER.store(true);
+    handler(f);
+    IN_HANDLER.store(false);
+
+    true
+}
+
+/// Returns the value of the stack pointer when a trap from the kernel occurs.
+fn trapped_stack_pointer(f: &TrapFrame) -> Vaddr {
+    // SAFETY: For a trap from the kernel, `f.rsp` is the address where the CPU has saved the
+    // stack pointer (see `trap.S`).
+    unsafe { core::ptr::read(f.rsp as *const usize) }
+}
+
+/// Handles a breakpoint exception from the kernel.
+///
+/// This function returns `false` if the breakpoint is not placed by kprobes.
+pub(super) fn handle_breakpoint(f: &mut TrapFrame) -> bool {
+    // The breakpoint exception is a trap, so RIP points to the instruction after `int3`.
+    let addr = f.rip - 1;
+
+    if addr == __kretprobe_trampoline as *const () as usize {
+        kretprobe::handle_return(f);
+        return true;
+    }
+
+    let probe = PROBE_POINTS.lock().get(&addr).cloned();
+    let Some(probe) = probe else {
+        // SAFETY: `addr` is the address of the `int3` instruction that has just been executed.
+        let byte = unsafe { core::ptr::read_volatile(addr as *const u8) };
+        if byte == INT3 {
+            return false;
+        }
+        // The kprobe has been unregistered after the breakpoint is hit.
+        f.rip = addr;
+        return true;
+    };
+    debug_assert!(STEPPING_PROBE.load().is_null());
+
+    let pre_handler = probe.pre_handler.as_ref();
+    if !pre_handler.is_none_or(|handler| call_handler(handler, f)) {
+        probe.nr_missed.fetch_add(1, Ordering::Relaxed);
+    }
+    if f.rip != addr + 1 {
+        // The pre-handler has changed the execution flow.
+        return true;
+    }
+
+    // The local IRQs are disabled during the single-step, so that the single-step is not
+    // interrupted and the slot is executed only once.
+    WAS_STEPPING_IRQ_ENABLED.store(f.rflags & INTERRUPT_FLAG != 0);
+    f.rflags = (f.rflags | TRAP_FLAG) & !INTERRUPT_FLAG;
+    f.rip = probe.slot.addr();
+    STEPPING_PROBE.store(Arc::into_raw(probe));
+
+    true
+}
+
+/// Handles a debug exception from the kernel.
+///
+/// This function returns `false` if the debug exception is not caused by kprobes.
+pub(super) fn handle_debug(f: &mut TrapFrame) -> bool {
+    let Some(probe) = finish_single_step(f) else {
+        return false;
+    };
+
+    let addr = probe.addr;
+    let slot_addr = probe.slot.addr();
+    let len = probe.insn.len();
+
+    // Jumps, calls, and returns may have moved RIP to their targets, which are correct.
+    // Otherwise, RIP points to the end of the slot and must be moved back.
+    if f.rip == slot_addr + len {
+        f.rip = addr + len;
+    }
+    if probe.insn.is_call() {
+        // SAFETY: The call instruction has just pushed the return address to the stack.
+        unsafe { core::ptr::write(trapped_stack_pointer(f) as *mut usize, addr + len) };
+    }
+
+    let post_handler = probe.post_handler.as_ref();
+    if !post_handler.is_none_or(|handler| call_handler(handler, f)) {
+        probe.nr_missed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    true
+}
+
+/// Fixes up the trap frame if an exception is raised while single-stepping out of line.
+///
+/// The exception will then be handled as if it were raised by the probed instruction. This
+/// function must be called for all exceptions except debug exceptions and NMIs, before the trap
+/// frame is used.
+pub(super) fn fixup_exception(f: &mut TrapFrame) {
+    let ptr = STEPPING_PROBE.load();
+    if ptr.is_null() {
+        return;
+    }
+    // SAFETY: The pointer is obtained from `Arc::into_raw` and has not been released.
+    let probe = unsafe { &*ptr };
+
+    let slot_addr = probe.slot.addr();
+    if !(slot_addr..slot_addr + probe.insn.len()).contains(&f.rip) {
+        return;
+    }
+
+    let offset = f.rip - slot_addr;
+    if let Some(probe) = finish_single_step(f) {
+        f.rip = probe.addr + offset;
+    }
+}
+
+/// Ends the single-step on this CPU and restores the flags.
+///
+/// This function returns the probe point whose instruction was single-stepped, or `None` if no
+/// single-step is in progress.
+fn finish_single_step(f: &mut TrapFrame) -> Option<Arc<ProbePoint>> {
+    let ptr = STEPPING_PROBE.load();
+    if ptr.is_null() {
+        return None;
+    }
+    STEPPING_PROBE.store(core::ptr::null());
+    // SAFETY: The pointer is obtained from `Arc::into_raw` and has not been released. It is
+    // released only once since `STEPPING_PROBE` has been cleared.
+    let probe = unsafe { Arc::from_raw(ptr) };
+
+    f.rflags &= !TRAP_FLAG;
+    if WAS_STEPPING_IRQ_ENABLED.load() {
+        f.rflags |= INTERRUPT_FLAG;
+    }
+
+    Some(probe)
+}
+
+#[cfg(ktest)]
+mod test {
+    use super::*;
+
+    #[inline(never)]
+    fn triple(x: usize) -> usize {
+        core::hint::black_box(x).wrapping_mul(3)
+    }
+
+    #[ktest]
+    fn kprobe_calls_handlers() {
+        static NR_PRE: AtomicUsize = AtomicUsize::new(0);
+        static NR_POST: AtomicUsize = AtomicUsize::new(0);
+
+        let pre_handler = |_: &mut TrapFrame| {
+            NR_PRE.fetch_add(1, Ordering::Relaxed);
+        };
+        let post_handler = |_: &mut TrapFrame| {
+            NR_POST.fetch_add(1, Ordering::Relaxed);
+        };
+        // SAFETY: `triple` is a function in the kernel text, which is not used by kprobes.
+        let kprobe = unsafe {
+            Kprobe::register(
+                triple as *const () as usize,
+                Some(Box::new(pre_handler)),
+                Some(Box::new(post_handler)),
+            )
+        }
+        .unwrap();
+
+        assert_eq!(triple(2), 6);
+        assert_eq!(NR_PRE.load(Ordering::Relaxed), 1);
+        assert_eq!(NR_POST.load(Ordering::Relaxed), 1);
+        assert_eq!(kprobe.nr_missed(), 0);
+
+        drop(kprobe);
+        assert_eq!(triple(3), 9);
+        assert_eq!(NR_PRE.load(Ordering::Relaxed), 1);
+    }
+
+    #[inline(never)]
+    fn double(x: usize) -> usize {
+        core::hint::black_box(x).wrapping_mul(2)
+    }
+
+    #[ktest]
+    fn kretprobe_calls_handlers() {
+        static NR_ENTRIES: AtomicUsize = AtomicUsize::new(0);
+        static RET_VALUE: AtomicUsize = AtomicUsize::new(0);
+
+        let entry_handler = |_: &mut TrapFrame| {
+            NR_ENTRIES.fetch_add(1, Ordering::Relaxed);
+        };
+        let return_handler = |f: &mut TrapFrame| {
+            RET_VALUE.store(f.rax, Ordering::Relaxed);
+        };
+        // SAFETY: `double` is a function in the kernel text, which is not used by kprobes.
+        let kretprobe = unsafe {
+            Kretprobe::register(
+                double as *const () as usize,
+                Some(Box::new(entry_handler)),
+                Box::new(return_handler),
+            )
+        }
+        .unwrap();
+
+        assert_eq!(double(21), 42);
+        assert_eq!(NR_ENTRIES.load(Ordering::Relaxed), 1);
+        assert_eq!(RET_VALUE.load(Ordering::Relaxed), 42);
+        assert_eq!(kretprobe.nr_missed(), 0);
+    }
+}
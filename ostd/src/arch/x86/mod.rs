@@ -4,16 +4,18 @@
 
 pub(crate) mod boot;
 pub mod cpu;
+pub mod crypto;
 pub mod device;
 pub(crate) mod io;
 pub(crate) mod iommu;
 pub mod irq;
 pub mod kernel;
+pub mod kprobe;
 pub(crate) mod mm;
-mod power;
+pub(crate) mod power;
 pub mod serial;
 pub(crate) mod task;
-mod timer;
+pub(crate) mod timer;
 pub mod trap;
 
 #[cfg(feature = "cvm_guest")]
@@ -0,0 +1,211 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Jumping to a new kernel, i.e., kexec.
+//!
+//! The destinations of the segments of the new kernel image may overlap with the memory used by
+//! the current kernel, including its code. So the segments are copied by the relocation code in
+//! `kexec.S`, which is copied to a control page that does not overlap with any destinations. The
+//! relocation code runs with a page table that identity-maps the physical memory, and then jumps
+//! to the entry of the new kernel in the 64-bit mode, as required by the Linux 64-bit boot
+//! protocol.
+//!
+//! Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/arch/x86/kernel/relocate_kernel_64.S>
+
+use core::{arch::global_asm, ops::Range};
+
+use crate::{
+    Result,
+    arch::{
+        if_tdx_enabled,
+        iommu::{has_dma_remapping, has_interrupt_remapping},
+    },
+    mm::{HasPaddr, PAGE_SIZE, Paddr, Segment, VmIo, kspace::kernel_loaded_offset},
+    power::{KexecImage, alloc_segment_avoiding},
+};
+
+global_asm!(include_str!("kexec.S"));
+
+unsafe extern "C" {
+    /// Switches to the page table and jumps to the relocation code in the control page.
+    fn __kexec_jump(
+        page_table: Paddr,
+        control_page: Paddr,
+        copy_list: Paddr,
+        boot_arg: usize,
+        entry: Paddr,
+    ) -> !;
+
+    static __kexec_relocate_start: u8;
+    static __kexec_relocate_end: u8;
+}
+
+/// The offset of the copy list in the control page.
+///
+/// The relocation code is placed before the copy list, and the stack is placed after it.
+const COPY_LIST_OFFSET: usize = PAGE_SIZE / 2;
+/// The size of each entry in the copy list.
+const COPY_ENTRY_SIZE: usize = 3 * size_of::<u64>();
+
+const PTE_PRESENT: u64 = 1 << 0;
+const PTE_WRITE: u64 = 1 << 1;
+const PTE_HUGE: u64 = 1 << 7;
+
+const NR_ENTRIES: usize = PAGE_SIZE / size_of::<u64>();
+/// The size of the memory mapped by a page directory with 2 MiB huge pages.
+const PD_COVERAGE: usize = 1 << 30;
+
+/// Returns whether a new kernel can be executed.
+pub(crate) fn can_kexec() -> bool {
+    // The TD guests cannot be reset to the 64-bit boot entry without the help of the TDX module.
+    if_tdx_enabled!({
+        return false;
+    });
+
+    // TODO: Disable the IOMMU before jumping to the new kernel.
+    !has_dma_remapping() && !has_interrupt_remapping()
+}
+
+/// The prepared control page and page table to jump to a new kernel.
+pub(crate) struct KexecJump {
+    control_page: Segment<()>,
+    page_table: Segment<()>,
+    entry: Paddr,
+    boot_arg: usize,
+}
+
+impl KexecJump {
+    /// Prepares the control page and the page table to jump to the new kernel.
+    ///
+    /// Neither the control page nor the page table overlaps with the destinations of the
+    /// segments of the image.
+    pub(crate) fn new(image: &KexecImage) -> Result<Self> {
+        let dests = image.dests();
+        let control_page = alloc_segment_avoiding(1, &dests)?;
+
+        // SAFETY: The relocation code is a valid memory region in the kernel code.
+        let code = unsafe {
+            let start = &raw const __kexec_relocate_start;
+            let len = (&raw const __kexec_relocate_end).addr() - start.addr();
+            core::slice::from_raw_parts(start, len)
+        };
+        assert!(code.len() <= COPY_LIST_OFFSET);
+        control_page.write_bytes(0, code).unwrap();
+
+        let mut offset = COPY_LIST_OFFSET;
+        for segment in image.segments() {
+            // The segments of a crash image are already at their destinations.
+            if segment.frames().paddr() == segment.dest() {
+                continue;
+            }
+            let entry = [
+                segment.frames().paddr() as u64,
+                segment.dest() as u64,
+                segment.len() as u64,
+            ];
+            control_page.write_val(offset, &entry).unwrap();
+            offset += COPY_ENTRY_SIZE;
+        }
+        control_page.write_val(offset, &[0u64; 3]).unwrap();
+
+        let page_table = new_identity_page_table(&dests)?;
+
+        Ok(Self {
+            control_page,
+            page_table,
+            entry: image.entry(),
+            boot_arg: image.boot_arg(),
+        })
+    }
+
+    /// Jumps to the new kernel.
+    ///
+    /// The caller must disable the local IRQs and stop the other CPUs.
+    pub(crate) fn jump(self) -> ! {
+        let control_page = self.control_page.paddr();
+
+        // SAFETY: The control page and the page table do not overlap with the destinations, so
+        // the relocation code can copy the segments and jump to the new kernel. The current
+        // kernel never runs again, so it does not matter that its memory is overwritten.
+        unsafe {
+            __kexec_jump(
+                self.page_table.paddr(),
+                control_page,
+                control_page + COPY_LIST_OFFSET,
+                self.boot_arg,
+                self.entry,
+            )
+        }
+    }
+}
+
+/// Creates a page table that identity-maps the physical memory and maps the kernel code at the
+/// kernel virtual addresses.
+///
+/// The memory is mapped with 2 MiB huge pages. The page table consists of a PML4 table, a PDPT
+/// for the kernel code, the PDPTs for the identity mapping, and the page directories.
+fn new_identity_page_table(dests: &[Range<Paddr>]) -> Result<Segment<()>> {
+    // Map at least the low 4 GiB, where the kernel code resides.
+    let top = crate::boot::boot_info()
+        .memory_regions
+        .iter()
+        .filter(|region| region.typ().is_physical())
+        .map(|region| region.end())
+        .chain(dests.iter().map(|dest| dest.end))
+        .fold(4 * PD_COVERAGE, usize::max);
+
+    let nr_pds = top.div_ceil(PD_COVERAGE);
+    let nr_pdpts = nr_pds.div_ceil(NR_ENTRIES);
+    let page_table = alloc_segment_avoiding(2 + nr_pdpts + nr_pds, dests)?;
+
+    let pml4 = page_table.paddr();
+    let kernel_pdpt = pml4 + PAGE_SIZE;
+    let pdpt = |index: usize| kernel_pdpt + PAGE_SIZE * (1 + index);
+    let pd = |index: usize| pdpt(nr_pdpts) + PAGE_SIZE * index;
+    let write_entry = |table: Paddr, index: usize, entry: u64| {
+        page_table
+            .write_val(table - pml4 + index * size_of::<u64>(), &entry)
+            .unwrap();
+    };
+
+    for pdpt_index in 0..nr_pdpts {
+        write_entry(
+            pml4,
+            pdpt_index,
+            pdpt(pdpt_index) as u64 | PTE_PRESENT | PTE_WRITE,
+        );
+    }
+    for pd_index in 0..nr_pds {
+        write_entry(
+            pdpt(pd_index / NR_ENTRIES),
+            pd_index % NR_ENTRIES,
+            pd(pd_index) as u64 | PTE_PRESENT | PTE_WRITE,
+        );
+        for index in 0..NR_ENTRIES {
+            let paddr = pd_index * PD_COVERAGE + index * (PD_COVERAGE / NR_ENTRIES);
+            write_entry(
+                pd(pd_index),
+                index,
+                paddr as u64 | PTE_PRESENT | PTE_WRITE | PTE_HUGE,
+            );
+        }
+    }
+
+    // Map the kernel code, which is loaded in the low 2 GiB, at the same virtual addresses.
+    let kernel_vaddr = kernel_loaded_offset();
+    let pml4_index = (kernel_vaddr >> 39) % NR_ENTRIES;
+    let pdpt_index = (kernel_vaddr >> 30) % NR_ENTRIES;
+    write_entry(
+        pml4,
+        pml4_index,
+        kernel_pdpt as u64 | PTE_PRESENT | PTE_WRITE,
+    );
+    for index in 0..2 {
+        write_entry(
+            kernel_pdpt,
+            pdpt_index + index,
+            pd(index) as u64 | PTE_PRESENT | PTE_WRITE,
+        );
+    }
+
+    Ok(page_table)
+}
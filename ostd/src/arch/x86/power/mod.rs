@@ -2,6 +2,12 @@
 
 //! Power management.
 
+mod kexec;
+mod sleep;
+
+pub(crate) use kexec::{KexecJump, can_kexec};
+pub(crate) use sleep::{can_suspend, suspend};
+
 mod qemu_isa_debug {
     //! The isa-debug-exit device in QEMU.
     //!
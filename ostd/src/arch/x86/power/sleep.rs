@@ -0,0 +1,124 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Suspend-to-RAM, i.e., the ACPI S3 sleeping state.
+//!
+//! In the S3 sleeping state, the CPUs are powered off while the memory is kept powered. When the
+//! system wakes up, the firmware jumps to the firmware waking vector in real mode. The vector is
+//! set to the AP boot code, which switches to long mode and then jumps to `__sleep_resume` in
+//! `sleep.S` to restore the CPU context saved before the system sleeps.
+//!
+//! Reference: <https://elixir.bootlin.com/linux/v6.16.5/source/arch/x86/kernel/acpi/sleep.c>
+
+use core::arch::global_asm;
+
+use x86::msr::{IA32_FS_BASE, IA32_KERNEL_GSBASE, IA32_TIME_STAMP_COUNTER, rdmsr, wrmsr};
+
+use crate::{
+    Error, Result,
+    arch::{
+        boot::smp::{AP_BOOT_START_PA, fill_boot_pt_ptr},
+        cpu::context::FpuContext,
+        if_tdx_enabled,
+        iommu::{has_dma_remapping, has_interrupt_remapping},
+        irq::{IRQ_CHIP, is_local_enabled},
+        kernel::{
+            acpi::set_firmware_waking_vector,
+            apic::{self, Apic},
+        },
+        read_tsc, timer, trap,
+    },
+    mm::{Paddr, kspace::kernel_loaded_offset},
+    task::disable_preempt,
+};
+
+global_asm!(include_str!("sleep.S"));
+
+unsafe extern "C" {
+    /// Saves the CPU context and calls `enter_sleep_state`.
+    ///
+    /// Returns non-zero if the system has slept and woken up, or zero if `enter_sleep_state`
+    /// returns.
+    fn __sleep_enter(enter_sleep_state: fn()) -> u64;
+
+    /// The root of the page table used by the AP boot code when the system wakes up.
+    static __sleep_wakeup_l4pt: u8;
+}
+
+/// Returns whether the CPUs can be suspended to RAM.
+pub(crate) fn can_suspend() -> bool {
+    // TD guests cannot be woken up via the firmware waking vector.
+    if_tdx_enabled!({
+        return false;
+    });
+
+    // TODO: Save and restore the state of the IOMMU.
+    !has_dma_remapping() && !has_interrupt_remapping()
+}
+
+/// Saves the CPU state, calls `enter_sleep_state` to put the system into the S3 sleeping state,
+/// and restores the CPU state after the system wakes up.
+///
+/// The caller must disable the local IRQs.
+///
+/// # Errors
+///
+/// This function fails if
+///  - other CPUs are online, with [`Error::NotEnoughResources`];
+///  - the firmware waking vector cannot be set, with [`Error::IoError`];
+///  - `enter_sleep_state` returns, which means that the system fails to sleep, with
+///    [`Error::IoError`].
+pub(crate) fn suspend(enter_sleep_state: fn()) -> Result<()> {
+    assert!(!is_local_enabled());
+
+    // TODO: Take other CPUs offline before sleeping, which requires CPU hotplug.
+    if crate::cpu::num_cpus() > 1 {
+        return Err(Error::NotEnoughResources);
+    }
+
+    set_firmware_waking_vector(AP_BOOT_START_PA as u32).map_err(|_| Error::IoError)?;
+
+    let wakeup_pt = (&raw const __sleep_wakeup_l4pt).addr() - kernel_loaded_offset();
+    // SAFETY: Other CPUs are offline, so there is no concurrent access to the AP boot code. The
+    // wakeup page table maps the AP boot code and the kernel code, as the AP boot code requires.
+    unsafe { fill_boot_pt_ptr(wakeup_pt as Paddr) };
+
+    // Save the CPU state that is not saved by `__sleep_enter`.
+    let mut fpu_context = FpuContext::new();
+    fpu_context.save();
+    // SAFETY: Reading these MSRs has no side effects.
+    let (fs_base, kernel_gs_base) = unsafe { (rdmsr(IA32_FS_BASE), rdmsr(IA32_KERNEL_GSBASE)) };
+    let irq_chip_state = IRQ_CHIP.get().unwrap().save_state();
+    let tsc_before_sleep = read_tsc();
+
+    // SAFETY: The callee-saved registers and the system registers are saved by `__sleep_enter`
+    // and restored by `__sleep_resume`, so the function returns as a normal function call.
+    let has_slept = unsafe { __sleep_enter(enter_sleep_state) } != 0;
+    if !has_slept {
+        return Err(Error::IoError);
+    }
+
+    // The firmware may reset the TSC. Do not let the TSC go backwards, or the monotonic time will
+    // go backwards as well. This also excludes the sleeping time from the monotonic time.
+    if read_tsc() < tsc_before_sleep {
+        // SAFETY: Setting the TSC to a larger value is safe.
+        unsafe { wrmsr(IA32_TIME_STAMP_COUNTER, tsc_before_sleep) };
+    }
+
+    crate::arch::enable_cpu_features();
+    // SAFETY: The local IRQs are disabled, so no preemption can occur. Note that this leaks the
+    // old GDT, which is acceptable since the system rarely sleeps.
+    unsafe { trap::init_on_cpu() };
+    // SAFETY: The FS base and the kernel GS base are restored to the values before sleeping.
+    unsafe {
+        wrmsr(IA32_FS_BASE, fs_base);
+        wrmsr(IA32_KERNEL_GSBASE, kernel_gs_base);
+    }
+    fpu_context.load();
+
+    let preempt_guard = disable_preempt();
+    apic::get_or_init(&preempt_guard as _).enable();
+    IRQ_CHIP.get().unwrap().restore_state(&irq_chip_state);
+    timer::resume();
+
+    Ok(())
+}
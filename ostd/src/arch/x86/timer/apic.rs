@@ -3,6 +3,7 @@
 use core::sync::atomic::{AtomicU64, Ordering};
 
 use log::info;
+use x86::msr::{IA32_TSC_DEADLINE, wrmsr};
 
 use crate::{
     arch::{
@@ -11,11 +12,19 @@ use crate::{
         trap::TrapFrame,
         tsc_freq,
     },
+    cpu_local_cell,
     irq::IrqLine,
     task::disable_preempt,
     timer::TIMER_FREQ,
 };
 
+cpu_local_cell! {
+    /// The TSC value at which the next tick is due on this CPU.
+    ///
+    /// This is only used in the TSC-deadline mode.
+    static TICK_DEADLINE: u64 = 0;
+}
+
 /// Initializes APIC with TSC-deadline mode or periodic mode.
 ///
 /// Return the corresponding [`IrqLine`] for the system timer.
@@ -38,20 +47,61 @@ pub(super) fn init_on_ap(timer_irq: &IrqLine) {
     init_timer(timer_irq);
 }
 
-/// A callback that needs to be called on timer interrupt.
-pub(super) fn timer_callback() {
-    use x86::msr::{IA32_TSC_DEADLINE, wrmsr};
-
+/// Checks whether a tick is due on timer interrupt.
+///
+/// In the TSC-deadline mode, the timer interrupt may be raised for a one-shot event before the
+/// next tick is due. If the tick is due, this function also sets the deadline of the next tick.
+pub(super) fn update_tick() -> bool {
     match CONFIG.get().expect("ACPI timer config is not initialized") {
         Config::DeadlineMode { tsc_interval } => {
             let tsc_value = crate::arch::read_tsc();
-            let next_tsc_value = tsc_interval + tsc_value;
-            unsafe { wrmsr(IA32_TSC_DEADLINE, next_tsc_value) };
+            if crate::timer::is_tick_stopped() || tsc_value < TICK_DEADLINE.load() {
+                return false;
+            }
+            TICK_DEADLINE.store(tsc_value + tsc_interval);
+            true
+        }
+        Config::PeriodicMode { .. } => true,
+    }
+}
+
+/// Programs the timer to fire at the next tick or the next one-shot event, whichever is earlier.
+///
+/// In the periodic mode, the timer cannot be programmed, so one-shot events are handled at ticks.
+pub(super) fn program_next_interrupt() {
+    match CONFIG.get().expect("ACPI timer config is not initialized") {
+        Config::DeadlineMode { .. } => {
+            let deadline = crate::timer::next_interrupt_deadline(TICK_DEADLINE.load());
+            // SAFETY: Writing the TSC deadline only affects when the next timer interrupt fires.
+            unsafe { wrmsr(IA32_TSC_DEADLINE, deadline) };
         }
         Config::PeriodicMode { .. } => {}
     }
 }
 
+/// Returns whether the APIC timer works in the TSC-deadline mode.
+pub(super) fn is_deadline_mode() -> bool {
+    matches!(CONFIG.get(), Some(Config::DeadlineMode { .. }))
+}
+
+/// Restarts the tick after it is stopped.
+///
+/// Returns the number of ticks that have been skipped.
+pub(super) fn restart_tick() -> u64 {
+    let Some(Config::DeadlineMode { tsc_interval }) = CONFIG.get() else {
+        return 0;
+    };
+
+    let tsc_value = crate::arch::read_tsc();
+    let tick_deadline = TICK_DEADLINE.load();
+    let skipped_ticks = tsc_value.saturating_sub(tick_deadline) / tsc_interval;
+    // The last skipped tick is due now, so it will be handled immediately.
+    TICK_DEADLINE.store(tick_deadline + skipped_ticks * tsc_interval);
+    program_next_interrupt();
+
+    skipped_ticks
+}
+
 /// Determines if the current system supports tsc_deadline mode APIC timer
 fn is_tsc_deadline_mode_supported() -> bool {
     use crate::arch::cpu::extension::{IsaExtensions, has_extensions};
@@ -77,7 +127,8 @@ fn init_deadline_mode(apic: &dyn Apic, timer_irq: &IrqLine) {
     // Enable TSC deadline mode
     apic.set_lvt_timer(timer_irq.num() as u64 | (1 << 18));
 
-    timer_callback();
+    update_tick();
+    program_next_interrupt();
 }
 
 fn init_periodic_mode(apic: &dyn Apic, timer_irq: &IrqLine, init_count: u64) {
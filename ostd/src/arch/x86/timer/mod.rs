@@ -30,8 +30,37 @@ pub(super) fn init_on_ap() {
     apic::init_on_ap(TIMER_IRQ.get().unwrap());
 }
 
+/// Enables timer interrupt again after this CPU wakes up from a sleeping state.
+pub(super) fn resume() {
+    apic::init_on_ap(TIMER_IRQ.get().unwrap());
+}
+
+/// Programs the timer to fire at the next tick or the next one-shot event, whichever is earlier.
+///
+/// The caller should disable local IRQs.
+pub(crate) fn program_next_interrupt() {
+    apic::program_next_interrupt();
+}
+
+/// Returns whether the tick can be stopped on idle CPUs.
+pub(crate) fn can_stop_tick() -> bool {
+    apic::is_deadline_mode()
+}
+
+/// Restarts the tick after it is stopped.
+///
+/// Returns the number of ticks that have been skipped.
+///
+/// The caller should disable local IRQs.
+pub(crate) fn restart_tick() -> u64 {
+    apic::restart_tick()
+}
+
 fn timer_callback(trapframe: &TrapFrame) {
-    crate::timer::call_timer_callback_functions(trapframe);
+    if apic::update_tick() {
+        crate::timer::call_timer_callback_functions(trapframe);
+    }
+    crate::timer::expire_event();
 
-    apic::timer_callback();
+    apic::program_next_interrupt();
 }
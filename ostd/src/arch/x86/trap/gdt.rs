@@ -4,6 +4,7 @@
 
 use alloc::boxed::Box;
 
+use x86::msr::{IA32_TSC_AUX, wrmsr};
 use x86_64::{
     PrivilegeLevel, VirtAddr,
     instructions::tables::{lgdt, load_tss},
@@ -18,7 +19,13 @@ use x86_64::{
     },
 };
 
-use crate::cpu::local::{CpuLocal, StaticCpuLocal};
+use crate::{
+    arch::cpu::extension::{IsaExtensions, has_extensions},
+    cpu::{
+        CpuId,
+        local::{CpuLocal, StaticCpuLocal},
+    },
+};
 
 /// Initializes and loads the GDT and TSS.
 ///
@@ -47,15 +54,22 @@ pub(super) unsafe fn init_on_cpu() {
     // intended for switching to a new kernel CS.
     assert_eq!(CS::get_reg(), KERNEL_CS);
 
-    // Allocate a new GDT with 8 entries.
+    // The CPU ID is accurate because the caller ensures that no preemption can occur.
+    let cpu_data = cpu_data(CpuId::current_racy());
+    let cpunode = cpunode_descriptor(cpu_data);
+
+    // Allocate a new GDT with 16 entries.
+    #[rustfmt::skip]
     let gdt = Box::new([
         0, KCODE64, KDATA, /* UCODE32 (not used) */ 0, UDATA, UCODE64, tss0, tss1,
+        0, 0, 0, 0, 0, 0, 0, cpunode,
     ]);
     let gdt = &*Box::leak(gdt);
     assert_eq!(gdt[KERNEL_CS.index() as usize], KCODE64);
     assert_eq!(gdt[KERNEL_SS.index() as usize], KDATA);
     assert_eq!(gdt[USER_CS.index() as usize], UCODE64);
     assert_eq!(gdt[USER_SS.index() as usize], UDATA);
+    assert_eq!(gdt[CPUNODE_INDEX], cpunode);
 
     // Load the new GDT.
     let gdtr = DescriptorTablePointer {
@@ -85,6 +99,39 @@ pub(super) unsafe fn init_on_cpu() {
     assert_eq!(gdt[(syscall.index() + 1) as usize], KDATA);
     // SAFETY: The selector points to correct kernel/user code/data descriptors in the GDT.
     unsafe { Star::write_raw(sysret.0, syscall.0) };
+
+    // Like Linux, also store the CPU data in `IA32_TSC_AUX`, which can be read by the `rdtscp`
+    // and `rdpid` instructions in user space.
+    if has_extensions(IsaExtensions::RDTSCP) || has_extensions(IsaExtensions::RDPID) {
+        // SAFETY: Writing `IA32_TSC_AUX` only changes the value returned by the `rdtscp` and
+        // `rdpid` instructions, which are not used by the kernel.
+        unsafe { wrmsr(IA32_TSC_AUX, cpu_data as u64) };
+    }
+}
+
+/// Returns the CPU data that user space can obtain without system calls.
+///
+/// As in Linux, the lower 12 bits are the CPU ID and the upper bits are the NUMA node ID. The
+/// vDSO function `__vdso_getcpu` reads the data from the segment limit of the `CPUNODE`
+/// descriptor (with the `lsl` instruction) or from `IA32_TSC_AUX` (with the `rdpid` instruction).
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.18/source/arch/x86/include/asm/segment.h>.
+fn cpu_data(cpu_id: CpuId) -> u32 {
+    // TODO: Support NUMA.
+    let node_id = 0;
+    (node_id << 12) | u32::from(cpu_id)
+}
+
+/// Returns the `CPUNODE` descriptor that stores the CPU data in its segment limit.
+///
+/// The descriptor is a present, read-only, expand-down, accessed data segment that can be
+/// accessed by user space. Its 20-bit segment limit is the CPU data.
+fn cpunode_descriptor(cpu_data: u32) -> u64 {
+    const CPUNODE_FLAGS: u64 = 0x0040_F500_0000_0000;
+
+    let limit_low = (cpu_data & 0xFFFF) as u64;
+    let limit_high = ((cpu_data >> 16) & 0xF) as u64;
+    CPUNODE_FLAGS | (limit_high << 48) | limit_low
 }
 
 // The linker script makes sure that the `.cpu_local_tss` section is at the beginning of the area
@@ -128,3 +175,6 @@ const KERNEL_SS: SegmentSelector = SegmentSelector::new(2, PrivilegeLevel::Ring0
 
 pub(super) const USER_CS: SegmentSelector = SegmentSelector::new(5, PrivilegeLevel::Ring3);
 pub(super) const USER_SS: SegmentSelector = SegmentSelector::new(4, PrivilegeLevel::Ring3);
+
+/// The index of the `CPUNODE` descriptor, which must match `GDT_ENTRY_CPUNODE` in Linux.
+const CPUNODE_INDEX: usize = 15;
@@ -28,6 +28,7 @@ use crate::{
     arch::{
         cpu::context::CpuException,
         irq::{HwIrqLine, disable_local, enable_local},
+        kprobe,
     },
     cpu::PrivilegeLevel,
     ex_table::ExTable,
@@ -146,12 +147,21 @@ unsafe extern "sysv64" fn trap_handler(f: &mut TrapFrame) {
         }
     }
 
+    let cpu_exception = CpuException::new(f.trap_num, f.error_code);
+
+    // An exception raised while single-stepping a probed instruction out of line should be
+    // handled as if it were raised by the probed instruction.
+    if cpu_exception.is_some_and(|exception| {
+        exception != CpuException::Debug && exception != CpuException::NonMaskableInterrupt
+    }) {
+        kprobe::fixup_exception(f);
+    }
+
     // The IRQ state before trapping. We need to ensure that the IRQ state
     // during exception handling is consistent with the state before the trap.
     let was_irq_enabled =
         f.rflags as u64 & x86_64::registers::rflags::RFlags::INTERRUPT_FLAG.bits() > 0;
 
-    let cpu_exception = CpuException::new(f.trap_num, f.error_code);
     match cpu_exception {
         #[cfg(feature = "cvm_guest")]
         Some(CpuException::VirtualizationException) => {
@@ -164,6 +174,8 @@ unsafe extern "sysv64" fn trap_handler(f: &mut TrapFrame) {
             *f = *trapframe_wrapper.0;
             disable_local_if(was_irq_enabled);
         }
+        Some(CpuException::BreakPoint) if kprobe::handle_breakpoint(f) => {}
+        Some(CpuException::Debug) if kprobe::handle_debug(f) => {}
         Some(CpuException::PageFault(raw_page_fault_info)) => {
             enable_local_if(was_irq_enabled);
             // The actual user space implementation should be responsible
@@ -31,6 +31,11 @@ pub enum Ns16550aRegister {
     ModemStat,
 }
 
+/// The frequency of the baud rate generator, divided by 16.
+///
+/// The baud rate is this value divided by the divisor latch.
+pub const NS16550A_BASE_BAUD: u32 = 115200;
+
 /// A trait that provides methods to access NS16550A registers.
 pub trait Ns16550aAccess {
     /// Reads from an NS16550A register.
@@ -55,6 +60,83 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// The bits in the Modem Control Register.
+    pub struct Ns16550aModemCtrl: u8 {
+        /// Data terminal ready (DTR).
+        const DTR   = 1 << 0;
+        /// Request to send (RTS).
+        const RTS   = 1 << 1;
+        /// Auxiliary output 1 (OUT1).
+        const OUT1  = 1 << 2;
+        /// Auxiliary output 2 (OUT2), which gates the IRQ line on PC-compatible machines.
+        const OUT2  = 1 << 3;
+        /// Loopback mode (LOOP).
+        const LOOP  = 1 << 4;
+    }
+}
+
+bitflags! {
+    /// The bits in the Modem Status Register.
+    pub struct Ns16550aModemStat: u8 {
+        /// Delta clear to send (DCTS).
+        const DCTS  = 1 << 0;
+        /// Delta data set ready (DDSR).
+        const DDSR  = 1 << 1;
+        /// Trailing edge ring indicator (TERI).
+        const TERI  = 1 << 2;
+        /// Delta data carrier detect (DDCD).
+        const DDCD  = 1 << 3;
+        /// Clear to send (CTS).
+        const CTS   = 1 << 4;
+        /// Data set ready (DSR).
+        const DSR   = 1 << 5;
+        /// Ring indicator (RI).
+        const RI    = 1 << 6;
+        /// Data carrier detect (DCD).
+        const DCD   = 1 << 7;
+    }
+}
+
+/// The parity of a NS16550A UART.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ns16550aParity {
+    /// No parity bit.
+    None,
+    /// Odd parity.
+    Odd,
+    /// Even parity.
+    Even,
+}
+
+/// The line configuration of a NS16550A UART.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ns16550aLineConfig {
+    /// The divisor of the baud rate generator.
+    ///
+    /// The baud rate is [`NS16550A_BASE_BAUD`] divided by the divisor. A zero divisor is treated
+    /// as one.
+    pub divisor: u16,
+    /// The number of data bits, from 5 to 8.
+    pub data_bits: u8,
+    /// The parity.
+    pub parity: Ns16550aParity,
+    /// Whether two stop bits (instead of one) are used.
+    pub two_stop_bits: bool,
+}
+
+impl Default for Ns16550aLineConfig {
+    /// Returns the configuration for 115200 bps, 8 data bits, no parity, and one stop bit.
+    fn default() -> Self {
+        Self {
+            divisor: 1,
+            data_bits: 8,
+            parity: Ns16550aParity::None,
+            two_stop_bits: false,
+        }
+    }
+}
+
 impl<A: Ns16550aAccess> Ns16550aUart<A> {
     /// Creates a new instance.
     pub const fn new(access: A) -> Self {
@@ -66,22 +148,84 @@ impl<A: Ns16550aAccess> Ns16550aUart<A> {
     /// This will set the baud rate to 115200 bps and configure IRQs to trigger when new data is
     /// received.
     pub fn init(&mut self) {
+        // Interrupt Enable: Disabled during the configuration.
+        self.access.write(Ns16550aRegister::IntEnOrDivisorHi, 0x00);
+
+        // Line Control: 115200 bps, 8-bit, no parity, one stop bit.
+        self.set_line_config(&Ns16550aLineConfig::default());
+        // FIFO Control: Enabled, both FIFOs cleared, IRQs triggered by every received byte.
+        self.access.write(Ns16550aRegister::FifoCtrl, 0x07);
+        // Modem Control: IRQs enabled, RTS/DTR set.
+        self.set_modem_ctrl(
+            Ns16550aModemCtrl::DTR | Ns16550aModemCtrl::RTS | Ns16550aModemCtrl::OUT2,
+        );
+        // Interrupt Enable: IRQs on received data.
+        self.access.write(Ns16550aRegister::IntEnOrDivisorHi, 0x01);
+    }
+
+    /// Probes whether the device exists.
+    ///
+    /// This checks whether the Interrupt Enable Register can hold the written values. Reading a
+    /// missing device typically returns all ones.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.18/source/drivers/tty/serial/8250/8250_port.c>.
+    pub fn probe(&mut self) -> bool {
+        let old_int_en = self.access.read(Ns16550aRegister::IntEnOrDivisorHi);
+
+        self.access.write(Ns16550aRegister::IntEnOrDivisorHi, 0x00);
+        let cleared = self.access.read(Ns16550aRegister::IntEnOrDivisorHi) & 0x0F;
+        self.access.write(Ns16550aRegister::IntEnOrDivisorHi, 0x0F);
+        let set = self.access.read(Ns16550aRegister::IntEnOrDivisorHi) & 0x0F;
+
+        self.access
+            .write(Ns16550aRegister::IntEnOrDivisorHi, old_int_en);
+
+        cleared == 0x00 && set == 0x0F
+    }
+
+    /// Sets the line configuration (e.g., the baud rate and the number of data bits).
+    pub fn set_line_config(&mut self, config: &Ns16550aLineConfig) {
         // Divisor Latch Access Bit.
         const DLAB: u8 = 0x80;
 
+        let [divisor_lo, divisor_hi] = config.divisor.max(1).to_le_bytes();
+
+        let word_len = config.data_bits.clamp(5, 8) - 5;
+        let stop_bits = if config.two_stop_bits { 1 << 2 } else { 0 };
+        let parity = match config.parity {
+            Ns16550aParity::None => 0,
+            Ns16550aParity::Odd => 1 << 3,
+            Ns16550aParity::Even => (1 << 3) | (1 << 4),
+        };
+        let line_ctrl = word_len | stop_bits | parity;
+
         // Baud Rate: 115200 bps / divisor
-        self.access.write(Ns16550aRegister::LineCtrl, DLAB);
-        self.access.write(Ns16550aRegister::DataOrDivisorLo, 0x01);
-        self.access.write(Ns16550aRegister::IntEnOrDivisorHi, 0x00);
+        self.access
+            .write(Ns16550aRegister::LineCtrl, line_ctrl | DLAB);
+        self.access
+            .write(Ns16550aRegister::DataOrDivisorLo, divisor_lo);
+        self.access
+            .write(Ns16550aRegister::IntEnOrDivisorHi, divisor_hi);
 
-        // Line Control: 8-bit, no parity, one stop bit.
-        self.access.write(Ns16550aRegister::LineCtrl, 0x03);
-        // FIFO Control: Disabled.
-        self.access.write(Ns16550aRegister::FifoCtrl, 0x00);
-        // Modem Control: IRQs enabled, RTS/DSR set.
-        self.access.write(Ns16550aRegister::ModemCtrl, 0x0B);
-        // Interrupt Enable: IRQs on received data.
-        self.access.write(Ns16550aRegister::IntEnOrDivisorHi, 0x01);
+        self.access.write(Ns16550aRegister::LineCtrl, line_ctrl);
+    }
+
+    /// Returns the bits in the Modem Control Register.
+    pub fn modem_ctrl(&self) -> Ns16550aModemCtrl {
+        Ns16550aModemCtrl::from_bits_truncate(self.access.read(Ns16550aRegister::ModemCtrl))
+    }
+
+    /// Sets the bits in the Modem Control Register.
+    pub fn set_modem_ctrl(&mut self, modem_ctrl: Ns16550aModemCtrl) {
+        self.access
+            .write(Ns16550aRegister::ModemCtrl, modem_ctrl.bits());
+    }
+
+    /// Returns the bits in the Modem Status Register.
+    ///
+    /// Note that reading the register clears the delta bits (e.g., [`Ns16550aModemStat::DCTS`]).
+    pub fn modem_stat(&self) -> Ns16550aModemStat {
+        Ns16550aModemStat::from_bits_truncate(self.access.read(Ns16550aRegister::ModemStat))
     }
 
     /// Sends a byte.
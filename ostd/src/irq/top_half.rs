@@ -2,7 +2,12 @@
 
 //! The top half of interrupt handling.
 
-use core::{fmt::Debug, ops::Deref};
+use alloc::sync::Weak;
+use core::{
+    fmt::Debug,
+    ops::Deref,
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+};
 
 use id_alloc::IdAlloc;
 use spin::Once;
@@ -10,16 +15,24 @@ use spin::Once;
 use crate::{
     Error,
     arch::{
-        irq::{HwIrqLine, IRQ_NUM_MAX, IRQ_NUM_MIN, IrqRemapping},
+        irq::{HwCpuId, HwIrqLine, IRQ_NUM_MAX, IRQ_NUM_MIN, IrqRemapping},
         trap::TrapFrame,
     },
+    cpu::CpuId,
     prelude::*,
+    smp::hw_cpu_id_of,
     sync::{RwLock, SpinLock, WriteIrqDisabled},
 };
 
 /// A type alias for the IRQ callback function.
 pub type IrqCallbackFunction = dyn Fn(&TrapFrame) + Sync + Send + 'static;
 
+/// A type alias for the function that reprograms the device when the affinity of an IRQ line
+/// is changed.
+///
+/// The argument is the new destination ID (see [`IrqLine::destination_id`]).
+pub type AffinityNotifierFunction = dyn Fn(u32) + Sync + Send + 'static;
+
 /// An Interrupt ReQuest (IRQ) line.
 ///
 /// Users can use [`alloc`] or [`alloc_specific`] to allocate a (specific) IRQ line.
@@ -58,15 +71,35 @@ impl IrqLine {
     }
 
     fn new(index: u8) -> Self {
-        let inner = InnerHandle { index };
+        let inner = Arc::new(InnerHandle { index });
         inner.remapping.init(index + IRQ_NUM_MIN);
+        *inner.handle.lock() = Arc::downgrade(&inner);
 
         Self {
-            inner: Arc::new(inner),
+            inner,
             callbacks: Vec::new(),
         }
     }
 
+    /// Looks up an allocated IRQ line by its number.
+    ///
+    /// The returned [`IrqLine`] has no callbacks. It can be used to query or change the states
+    /// that are shared by all the handles of the IRQ line, e.g., the affinity.
+    pub fn lookup(irq_num: u8) -> Option<Self> {
+        if irq_num < IRQ_NUM_MIN {
+            return None;
+        }
+
+        let inner = INNERS[(irq_num - IRQ_NUM_MIN) as usize]
+            .handle
+            .lock()
+            .upgrade()?;
+        Some(Self {
+            inner,
+            callbacks: Vec::new(),
+        })
+    }
+
     /// Gets the IRQ number.
     pub fn num(&self) -> u8 {
         self.inner.index + IRQ_NUM_MIN
@@ -107,6 +140,75 @@ impl IrqLine {
     pub fn remapping_index(&self) -> Option<u16> {
         self.inner.remapping.remapping_index()
     }
+
+    /// Returns the CPU that the interrupts are delivered to.
+    ///
+    /// The interrupts are delivered to the BSP unless the affinity is changed by
+    /// [`Self::set_affinity`].
+    pub fn affinity(&self) -> CpuId {
+        let raw_cpu_id = self.inner.affinity.load(Ordering::Relaxed);
+        CpuId::try_from(raw_cpu_id as usize).unwrap()
+    }
+
+    /// Sets the CPU that the interrupts are delivered to.
+    ///
+    /// If interrupt remapping is enabled, the remapping entry is updated. Otherwise, the device
+    /// must be reprogrammed with the new [`Self::destination_id`], which is done by the
+    /// function registered with [`Self::set_affinity_notifier`]. If there is no such function,
+    /// the affinity cannot be changed and this method fails with [`Error::AccessDenied`].
+    pub fn set_affinity(&self, cpu_id: CpuId) -> Result<()> {
+        let Some(hw_cpu_id) = hw_cpu_id_of(cpu_id) else {
+            return Err(Error::InvalidArgs);
+        };
+
+        let notifier = self.inner.affinity_notifier.lock();
+        let is_remapped = self.remapping_index().is_some();
+        if !is_remapped && notifier.is_none() {
+            return Err(Error::AccessDenied);
+        }
+
+        self.inner
+            .affinity
+            .store(u32::from(cpu_id), Ordering::Relaxed);
+        if is_remapped {
+            self.inner.remapping.set_destination(self.num(), hw_cpu_id);
+        } else {
+            (notifier.as_ref().unwrap())(hw_cpu_id.as_u32());
+        }
+
+        Ok(())
+    }
+
+    /// Returns the architecture-specific ID of the CPU that the interrupts are delivered to.
+    ///
+    /// On x86-64, this is the APIC ID, which should be encoded in the interrupt messages if
+    /// interrupt remapping is disabled.
+    pub fn destination_id(&self) -> u32 {
+        hw_cpu_id_of(self.affinity()).map_or(0, HwCpuId::as_u32)
+    }
+
+    /// Registers the function that reprograms the device when the affinity is changed.
+    ///
+    /// The function is shared by all the handles of the IRQ line, and replaces the old one (if
+    /// any). It is unregistered automatically when the IRQ line is released.
+    pub fn set_affinity_notifier(&self, notifier: Option<Box<AffinityNotifierFunction>>) {
+        *self.inner.affinity_notifier.lock() = notifier;
+    }
+
+    /// Returns whether the affinity is managed by the kernel.
+    ///
+    /// A managed affinity is set by the driver, e.g., to spread the interrupts of the queues
+    /// across CPUs, and should not be changed by the users.
+    pub fn is_affinity_managed(&self) -> bool {
+        self.inner.is_affinity_managed.load(Ordering::Relaxed)
+    }
+
+    /// Marks whether the affinity is managed by the kernel.
+    pub fn set_affinity_managed(&self, is_managed: bool) {
+        self.inner
+            .is_affinity_managed
+            .store(is_managed, Ordering::Relaxed);
+    }
 }
 
 impl Clone for IrqLine {
@@ -121,6 +223,12 @@ impl Clone for IrqLine {
 struct Inner {
     callbacks: RwLock<Vec<Box<IrqCallbackFunction>>, WriteIrqDisabled>,
     remapping: IrqRemapping,
+    /// The handle of the allocated IRQ line, which is used by [`IrqLine::lookup`].
+    handle: SpinLock<Weak<InnerHandle>>,
+    /// The raw ID of the CPU that the interrupts are delivered to.
+    affinity: AtomicU32,
+    is_affinity_managed: AtomicBool,
+    affinity_notifier: SpinLock<Option<Box<AffinityNotifierFunction>>>,
 }
 
 impl Inner {
@@ -128,7 +236,23 @@ impl Inner {
         Self {
             callbacks: RwLock::new(Vec::new()),
             remapping: IrqRemapping::new(),
+            handle: SpinLock::new(Weak::new()),
+            affinity: AtomicU32::new(0),
+            is_affinity_managed: AtomicBool::new(false),
+            affinity_notifier: SpinLock::new(None),
+        }
+    }
+
+    /// Resets the states that are shared by the handles, before the IRQ line is released.
+    fn reset(&self, irq_num: u8) {
+        let bsp = CpuId::bsp();
+        self.affinity.store(u32::from(bsp), Ordering::Relaxed);
+        if let Some(hw_cpu_id) = hw_cpu_id_of(bsp) {
+            self.remapping.set_destination(irq_num, hw_cpu_id);
         }
+        self.is_affinity_managed.store(false, Ordering::Relaxed);
+        *self.affinity_notifier.lock() = None;
+        *self.handle.lock() = Weak::new();
     }
 }
 
@@ -160,6 +284,7 @@ impl Deref for InnerHandle {
 
 impl Drop for InnerHandle {
     fn drop(&mut self) {
+        self.reset(self.index + IRQ_NUM_MIN);
         ALLOCATOR.get().unwrap().lock().free(self.index as usize);
     }
 }
@@ -105,6 +105,9 @@ unsafe fn init() {
     // SAFETY: This function is called only once on the BSP.
     unsafe { mm::kspace::activate_kernel_page_table() };
 
+    #[cfg(feature = "kasan_shadow")]
+    mm::heap::kasan::init_shadow();
+
     sync::init();
 
     boot::init_after_heap();
@@ -112,6 +115,8 @@ unsafe fn init() {
     // SAFETY: This function is called only once on the BSP.
     unsafe { arch::late_init_on_bsp() };
 
+    timer::init();
+
     #[cfg(target_arch = "x86_64")]
     arch::if_tdx_enabled!({
         arch::serial::init();
@@ -19,6 +19,7 @@ use crate::{
 /// Options for allocating physical memory frames.
 pub struct FrameAllocOptions {
     zeroed: bool,
+    movable: bool,
 }
 
 impl Default for FrameAllocOptions {
@@ -30,7 +31,10 @@ impl Default for FrameAllocOptions {
 impl FrameAllocOptions {
     /// Creates new options for allocating the specified number of frames.
     pub fn new() -> Self {
-        Self { zeroed: true }
+        Self {
+            zeroed: true,
+            movable: false,
+        }
     }
 
     /// Sets whether the allocated frames should be initialized with zeros.
@@ -45,6 +49,21 @@ impl FrameAllocOptions {
         self
     }
 
+    /// Sets whether the allocated frame is movable.
+    ///
+    /// A movable frame can be migrated to another frame by its owner during
+    /// memory compaction (see [`super::compaction`]). The hint allows the
+    /// allocator to group movable frames together, which keeps the
+    /// unmovable frames from scattering and leaves more memory that can be
+    /// compacted for high-order allocations.
+    ///
+    /// The hint only applies to [`Self::alloc_frame`] and
+    /// [`Self::alloc_frame_with`]. By default, the frame is not movable.
+    pub fn movable(&mut self, movable: bool) -> &mut Self {
+        self.movable = movable;
+        self
+    }
+
     /// Allocates a single untyped frame without metadata.
     pub fn alloc_frame(&self) -> Result<Frame<()>> {
         self.alloc_frame_with(())
@@ -53,8 +72,7 @@ impl FrameAllocOptions {
     /// Allocates a single frame with additional metadata.
     pub fn alloc_frame_with<M: AnyFrameMeta>(&self, metadata: M) -> Result<Frame<M>> {
         let single_layout = Layout::from_size_align(PAGE_SIZE, PAGE_SIZE).unwrap();
-        let frame = get_global_frame_allocator()
-            .alloc(single_layout)
+        let frame = alloc_or_reclaim(single_layout, self.movable)
             .map(|paddr| Frame::from_unused(paddr, metadata).unwrap())
             .ok_or(Error::NoMemory)?;
 
@@ -67,6 +85,54 @@ impl FrameAllocOptions {
         Ok(frame)
     }
 
+    /// Allocates multiple untyped frames without metadata.
+    ///
+    /// The frames are not necessarily contiguous. They are allocated in bulk,
+    /// which is faster than allocating them one by one.
+    pub fn alloc_frames(&self, nframes: usize) -> Result<Vec<Frame<()>>> {
+        self.alloc_frames_with(nframes, |_| ())
+    }
+
+    /// Allocates multiple frames with additional metadata.
+    ///
+    /// The frames are not necessarily contiguous. The closure receives the
+    /// physical address of each frame and returns its metadata.
+    pub fn alloc_frames_with<M: AnyFrameMeta, F>(
+        &self,
+        nframes: usize,
+        mut metadata_fn: F,
+    ) -> Result<Vec<Frame<M>>>
+    where
+        F: FnMut(Paddr) -> M,
+    {
+        let single_layout = Layout::from_size_align(PAGE_SIZE, PAGE_SIZE).unwrap();
+        let mut frames = Vec::with_capacity(nframes);
+        let mut paddrs = [0; BULK_BATCH_SIZE];
+
+        while frames.len() < nframes {
+            let nr_to_alloc = (nframes - frames.len()).min(BULK_BATCH_SIZE);
+            let nr_allocated = alloc_bulk_or_shrink(single_layout, &mut paddrs[..nr_to_alloc]);
+            for &paddr in paddrs[..nr_allocated].iter() {
+                frames.push(Frame::from_unused(paddr, metadata_fn(paddr)).unwrap());
+            }
+            if nr_allocated < nr_to_alloc {
+                // The allocated frames are dropped and returned in bulk.
+                drop_frames(frames);
+                return Err(Error::NoMemory);
+            }
+        }
+
+        if self.zeroed {
+            for frame in frames.iter() {
+                let addr = paddr_to_vaddr(frame.paddr()) as *mut u8;
+                // SAFETY: The newly allocated frame is guaranteed to be valid.
+                unsafe { core::ptr::write_bytes(addr, 0, PAGE_SIZE) }
+            }
+        }
+
+        Ok(frames)
+    }
+
     /// Allocates a contiguous range of untyped frames without metadata.
     pub fn alloc_segment(&self, nframes: usize) -> Result<Segment<()>> {
         self.alloc_segment_with(nframes, |_| ())
@@ -88,8 +154,7 @@ impl FrameAllocOptions {
             return Err(Error::InvalidArgs);
         }
         let layout = Layout::from_size_align(nframes * PAGE_SIZE, PAGE_SIZE).unwrap();
-        let segment = get_global_frame_allocator()
-            .alloc(layout)
+        let segment = alloc_or_reclaim(layout, false)
             .map(|start| {
                 Segment::from_unused(start..start + nframes * PAGE_SIZE, metadata_fn).unwrap()
             })
@@ -166,6 +231,51 @@ pub trait GlobalFrameAllocator: Sync {
     /// The deallocated memory can be uninitialized.
     fn dealloc(&self, addr: Paddr, size: usize);
 
+    /// Allocates a contiguous range of frames that are movable.
+    ///
+    /// The frames are allocated like [`GlobalFrameAllocator::alloc`], and
+    /// are also deallocated with [`GlobalFrameAllocator::dealloc`]. But the
+    /// allocator is hinted that their owners can migrate them during memory
+    /// compaction (see [`super::compaction`]), so it can group them
+    /// together to avoid fragmentation.
+    ///
+    /// The default implementation calls [`GlobalFrameAllocator::alloc`].
+    fn alloc_movable(&self, layout: Layout) -> Option<Paddr> {
+        self.alloc(layout)
+    }
+
+    /// Allocates multiple contiguous ranges of frames with the same layout.
+    ///
+    /// The addresses of the allocated ranges are written to `addrs`. It
+    /// returns the number of allocated ranges, which is less than
+    /// `addrs.len()` only if the memory is exhausted.
+    ///
+    /// The default implementation calls [`GlobalFrameAllocator::alloc`]
+    /// repeatedly. Allocators can override it to amortize the cost of locking
+    /// and refilling their caches.
+    fn alloc_bulk(&self, layout: Layout, addrs: &mut [Paddr]) -> usize {
+        for (i, addr) in addrs.iter_mut().enumerate() {
+            let Some(allocated) = self.alloc(layout) else {
+                return i;
+            };
+            *addr = allocated;
+        }
+        addrs.len()
+    }
+
+    /// Deallocates multiple contiguous ranges of frames with the same size.
+    ///
+    /// Each range is deallocated as if by [`GlobalFrameAllocator::dealloc`].
+    ///
+    /// The default implementation calls [`GlobalFrameAllocator::dealloc`]
+    /// repeatedly. Allocators can override it to amortize the cost of locking
+    /// and draining their caches.
+    fn dealloc_bulk(&self, addrs: &[Paddr], size: usize) {
+        for &addr in addrs {
+            self.dealloc(addr, size);
+        }
+    }
+
     /// Adds a contiguous range of frames to the allocator.
     ///
     /// The memory being added must never overlap with any memory that was
@@ -173,6 +283,23 @@ pub trait GlobalFrameAllocator: Sync {
     ///
     /// The added memory can be uninitialized.
     fn add_free_memory(&self, addr: Paddr, size: usize);
+
+    /// Returns whether the frame at `addr` is known to be free.
+    ///
+    /// Allocators may own the free frames with [`UniqueFrame`]s, e.g., to
+    /// keep free lists in their metadata. Memory compaction asks the
+    /// allocator to tell such frames from the frames uniquely owned by
+    /// others, which cannot be migrated. The answer may be conservative: a
+    /// free frame can be reported as not free, but a frame that is not free
+    /// must never be reported as free.
+    ///
+    /// The default implementation returns `false`, so a page block that
+    /// contains any uniquely owned frames is never evacuated.
+    ///
+    /// [`UniqueFrame`]: super::unique::UniqueFrame
+    fn is_free(&self, _addr: Paddr) -> bool {
+        false
+    }
 }
 
 unsafe extern "Rust" {
@@ -188,6 +315,81 @@ pub(super) fn get_global_frame_allocator() -> &'static dyn GlobalFrameAllocator
     unsafe { __GLOBAL_FRAME_ALLOCATOR_REF }
 }
 
+/// Allocates frames from the global frame allocator.
+///
+/// If the allocation fails, the shrinkers are called to release cached memory
+/// and the allocation is retried once. If it still fails for multiple frames,
+/// the memory may be too fragmented, so the memory is compacted and the
+/// allocation is retried again.
+fn alloc_or_reclaim(layout: Layout, movable: bool) -> Option<Paddr> {
+    let allocator = get_global_frame_allocator();
+    let alloc = || {
+        if movable {
+            allocator.alloc_movable(layout)
+        } else {
+            allocator.alloc(layout)
+        }
+    };
+
+    if let Some(paddr) = alloc() {
+        return Some(paddr);
+    }
+
+    if super::shrinker::shrink_all() > 0
+        && let Some(paddr) = alloc()
+    {
+        return Some(paddr);
+    }
+
+    if layout.size() <= PAGE_SIZE || !super::compaction::compact_for(layout) {
+        return None;
+    }
+    alloc()
+}
+
+/// Allocates frames in bulk from the global frame allocator.
+///
+/// It behaves like [`alloc_or_reclaim`] but for [`GlobalFrameAllocator::alloc_bulk`],
+/// except that the memory is never compacted since only single frames are
+/// allocated.
+fn alloc_bulk_or_shrink(layout: Layout, addrs: &mut [Paddr]) -> usize {
+    let allocator = get_global_frame_allocator();
+    let nr_allocated = allocator.alloc_bulk(layout, addrs);
+    if nr_allocated == addrs.len() || super::shrinker::shrink_all() == 0 {
+        return nr_allocated;
+    }
+    nr_allocated + allocator.alloc_bulk(layout, &mut addrs[nr_allocated..])
+}
+
+/// The maximum number of frames that are allocated or deallocated in a batch.
+const BULK_BATCH_SIZE: usize = 32;
+
+/// Drops the frame handles and returns the frames in bulk to the allocator.
+///
+/// It is equivalent to dropping the handles one by one, but is faster when
+/// many handles are the last ones to their frames.
+pub fn drop_frames<M: AnyFrameMeta + ?Sized>(frames: impl IntoIterator<Item = Frame<M>>) {
+    let mut paddrs = [0; BULK_BATCH_SIZE];
+    let mut nr_freed = 0;
+
+    for frame in frames {
+        let paddr = frame.paddr();
+        if !frame.drop_without_dealloc() {
+            continue;
+        }
+        paddrs[nr_freed] = paddr;
+        nr_freed += 1;
+        if nr_freed == BULK_BATCH_SIZE {
+            get_global_frame_allocator().dealloc_bulk(&paddrs, PAGE_SIZE);
+            nr_freed = 0;
+        }
+    }
+
+    if nr_freed > 0 {
+        get_global_frame_allocator().dealloc_bulk(&paddrs[..nr_freed], PAGE_SIZE);
+    }
+}
+
 /// Initializes the global frame allocator.
 ///
 /// It just does adds the frames to the global frame allocator. Calling it
@@ -203,17 +405,24 @@ pub(crate) unsafe fn init() {
     let early_allocator = EARLY_ALLOCATOR.lock().take().unwrap();
     let (range_1, range_2) = early_allocator.allocated_regions();
 
+    // Keep the memory reserved for the crash kernel away from the frame allocator.
+    let crash_kernel_range =
+        crate::power::reserve_crash_kernel_region(&[range_1.clone(), range_2.clone()]);
+
     for region in regions.iter() {
         if region.typ() == MemoryRegionType::Usable {
             debug_assert!(region.base().is_multiple_of(PAGE_SIZE));
             debug_assert!(region.len().is_multiple_of(PAGE_SIZE));
 
             // Add global free pages to the frame allocator.
-            // Truncate the early allocated frames if there is an overlap.
+            // Truncate the early allocated frames and the crash kernel memory if there is an
+            // overlap.
             for r1 in range_difference(&(region.base()..region.end()), &range_1) {
                 for r2 in range_difference(&r1, &range_2) {
-                    log::info!("Adding free frames to the allocator: {:x?}", r2);
-                    get_global_frame_allocator().add_free_memory(r2.start, r2.len());
+                    for r3 in range_difference(&r2, &crash_kernel_range) {
+                        log::info!("Adding free frames to the allocator: {:x?}", r3);
+                        get_global_frame_allocator().add_free_memory(r3.start, r3.len());
+                    }
                 }
             }
         }
@@ -0,0 +1,279 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Memory compaction.
+//!
+//! An allocation of multiple contiguous frames (e.g., for huge pages or large
+//! DMA buffers) may fail even if there are plenty of free frames, because the
+//! free frames are scattered among the in-use ones. Memory compaction
+//! evacuates the in-use frames in a block of [`PAGEBLOCK_SIZE`] by migrating
+//! them to other frames, so that the whole block can be freed and coalesced.
+//!
+//! Only the owner of a frame knows where the references to the frame are, so
+//! only the owner can migrate the frame. The owner of the frames with a type
+//! of metadata registers a [`FrameMigrator`] for the type, which replaces its
+//! references to a frame with the references to a new frame using
+//! [`migrate_frame`]. Such frames should be allocated as movable frames (see
+//! [`FrameAllocOptions::movable`]).
+//!
+//! The memory is compacted when an allocation of multiple frames fails
+//! outside the atomic mode, or when [`compact_memory`] is called.
+//!
+//! [`FrameAllocOptions::movable`]: super::allocator::FrameAllocOptions::movable
+
+use core::{
+    alloc::Layout,
+    any::{Any, TypeId},
+    ops::Range,
+    sync::atomic::Ordering,
+};
+
+use align_ext::AlignExt;
+
+use super::{
+    Frame,
+    allocator::get_global_frame_allocator,
+    max_paddr,
+    meta::{AnyFrameMeta, GetFrameError},
+    untyped::AnyUFrameMeta,
+};
+use crate::{
+    mm::{HasPaddr, PAGE_SIZE, Paddr, io::util::HasVmReaderWriter},
+    prelude::*,
+    sync::{LocalIrqDisabled, Mutex, RwLock, SpinLock, synchronize_rcu},
+    task::{atomic_mode::is_in_atomic_mode, disable_preempt},
+};
+
+/// The number of frames in a block that is evacuated as a whole.
+const NR_FRAMES_PER_PAGEBLOCK: usize = 512;
+
+/// The size of a block that is evacuated as a whole.
+///
+/// It is the size of a huge page with 4 KiB base pages, i.e., 2 MiB. The
+/// frame allocator may also group the movable frames in blocks of this size.
+pub const PAGEBLOCK_SIZE: usize = NR_FRAMES_PER_PAGEBLOCK * PAGE_SIZE;
+
+/// The owner of movable frames, which can migrate them.
+pub trait FrameMigrator: Sync {
+    /// Migrates an in-use frame whose metadata is of the registered type.
+    ///
+    /// The migrator should replace all its references to the frame with the
+    /// references to a new frame using [`migrate_frame`]. It returns whether
+    /// the frame is migrated.
+    ///
+    /// The method is called outside the atomic mode.
+    fn migrate(&self, frame: Frame<dyn AnyFrameMeta>) -> bool;
+}
+
+static MIGRATORS: RwLock<Vec<(TypeId, &'static dyn FrameMigrator)>, LocalIrqDisabled> =
+    RwLock::new(Vec::new());
+
+/// Registers a migrator for the frames whose metadata is of type `M`.
+pub fn register_frame_migrator<M: AnyFrameMeta>(migrator: &'static dyn FrameMigrator) {
+    MIGRATORS.write().push((TypeId::of::<M>(), migrator));
+}
+
+fn find_migrator(frame: &Frame<dyn AnyFrameMeta>) -> Option<&'static dyn FrameMigrator> {
+    let type_id = (frame.dyn_meta() as &dyn Any).type_id();
+    MIGRATORS
+        .read()
+        .iter()
+        .find(|(id, _)| *id == type_id)
+        .map(|(_, migrator)| *migrator)
+}
+
+/// Migrates an untyped frame to a new frame.
+///
+/// It allocates a new frame with `metadata` and copies the contents of
+/// `frame` to the new frame. Then `replace` is called with the new frame,
+/// which should replace the caller's references to `frame` with the
+/// references to the new frame. It returns whether the frame is migrated.
+///
+/// The caller must own exactly `nr_refs` references to `frame`, including
+/// `frame` itself, and must prevent new references to `frame` from being
+/// created except with [`Frame::try_clone`] (e.g., by holding a lock). If
+/// there are other references, e.g., from the page tables that map the frame,
+/// the frame is not migrated.
+///
+/// During the migration, the reference count of `frame` is frozen so that
+/// [`Frame::try_clone`] fails. `replace` is called in the atomic mode and must
+/// not drop any references to `frame`. But it can retire the references via
+/// RCU, e.g., by replacing them in an RCU-protected data structure.
+pub fn migrate_frame<M: AnyUFrameMeta>(
+    frame: &Frame<M>,
+    nr_refs: u64,
+    metadata: M,
+    replace: impl FnOnce(Frame<M>),
+) -> bool {
+    let Some(paddr) = alloc_target() else {
+        return false;
+    };
+    let new_frame = Frame::from_unused(paddr, metadata).unwrap();
+
+    // Disabling preemption ensures that the references retired via RCU in
+    // `replace` are not dropped before the reference count is unfrozen.
+    let preempt_guard = disable_preempt();
+    if !frame.slot().freeze_ref_count(nr_refs) {
+        return false;
+    }
+
+    new_frame.writer().write(&mut frame.reader());
+    replace(new_frame);
+
+    // SAFETY: The reference count is frozen above with `nr_refs`.
+    unsafe { frame.slot().unfreeze_ref_count(nr_refs) };
+    drop(preempt_guard);
+
+    true
+}
+
+/// Compacts all the memory.
+///
+/// It evacuates every block of [`PAGEBLOCK_SIZE`] whose in-use frames are all
+/// movable. It returns the number of evacuated blocks.
+///
+/// # Panics
+///
+/// This function will panic if it is called in the atomic mode.
+#[track_caller]
+pub fn compact_memory() -> usize {
+    crate::task::atomic_mode::might_sleep();
+
+    compact(usize::MAX)
+}
+
+/// Compacts the memory for a failed allocation of multiple frames.
+///
+/// It returns whether any blocks are evacuated, in which case the allocation
+/// should be retried. The memory is not compacted in the atomic mode.
+pub(super) fn compact_for(layout: Layout) -> bool {
+    if crate::IN_BOOTSTRAP_CONTEXT.load(Ordering::Relaxed) || is_in_atomic_mode() {
+        return false;
+    }
+
+    compact(layout.size().div_ceil(PAGEBLOCK_SIZE)) > 0
+}
+
+/// Serializes the compaction.
+static COMPACTION: Mutex<()> = Mutex::new(());
+
+/// Compacts the memory until `nr_blocks` blocks are evacuated.
+///
+/// It returns the number of evacuated blocks.
+fn compact(nr_blocks: usize) -> usize {
+    let compaction_guard = COMPACTION.lock();
+
+    let mut nr_evacuated = 0;
+    for start in (0..max_paddr().align_down(PAGEBLOCK_SIZE)).step_by(PAGEBLOCK_SIZE) {
+        if nr_evacuated == nr_blocks {
+            break;
+        }
+        if evacuate(start..start + PAGEBLOCK_SIZE) {
+            nr_evacuated += 1;
+        }
+    }
+
+    drop(compaction_guard);
+
+    // The owners may retire the references to the migrated frames via RCU.
+    // Wait for them to be dropped so that the evacuated blocks are freed.
+    if nr_evacuated > 0 {
+        synchronize_rcu();
+    }
+
+    nr_evacuated
+}
+
+/// Evacuates a block by migrating its in-use frames.
+///
+/// It returns whether all the in-use frames are migrated.
+fn evacuate(block: Range<Paddr>) -> bool {
+    if !is_evacuable(&block) {
+        return false;
+    }
+
+    *EVACUATION.lock() = Some(Evacuation {
+        block: block.clone(),
+        held: [0; NR_FRAMES_PER_PAGEBLOCK],
+        nr_held: 0,
+    });
+
+    let is_evacuated = block
+        .step_by(PAGE_SIZE)
+        .all(|paddr| match Frame::from_in_use(paddr) {
+            Ok(frame) => find_migrator(&frame).is_some_and(|migrator| migrator.migrate(frame)),
+            Err(GetFrameError::Unused) => true,
+            Err(GetFrameError::Unique) => is_free_unique(paddr),
+            Err(_) => false,
+        });
+
+    let mut evacuation = EVACUATION.lock();
+    if let Some(Evacuation { held, nr_held, .. }) = evacuation.as_ref() {
+        get_global_frame_allocator().dealloc_bulk(&held[..*nr_held], PAGE_SIZE);
+    }
+    *evacuation = None;
+
+    is_evacuated
+}
+
+/// Returns whether the block has in-use frames and all of them are movable.
+fn is_evacuable(block: &Range<Paddr>) -> bool {
+    let mut has_in_use = false;
+
+    for paddr in block.clone().step_by(PAGE_SIZE) {
+        match Frame::from_in_use(paddr) {
+            Ok(frame) => {
+                if find_migrator(&frame).is_none() {
+                    return false;
+                }
+                has_in_use = true;
+            }
+            Err(GetFrameError::Unused) => {}
+            Err(GetFrameError::Unique) if is_free_unique(paddr) => {}
+            Err(_) => return false,
+        }
+    }
+
+    has_in_use
+}
+
+/// Returns whether a uniquely owned frame is free in the allocator.
+///
+/// The allocator may keep free frames as [`UniqueFrame`]s, which need not be
+/// migrated. Other uniquely owned frames have no migrators and cannot be
+/// migrated, so the blocks containing them must not be evacuated.
+///
+/// [`UniqueFrame`]: super::unique::UniqueFrame
+fn is_free_unique(paddr: Paddr) -> bool {
+    get_global_frame_allocator().is_free(paddr)
+}
+
+/// A block that is being evacuated.
+struct Evacuation {
+    block: Range<Paddr>,
+    /// The frames in the block that are allocated as migration targets.
+    ///
+    /// They are held until the evacuation ends, so that no frames are
+    /// migrated into the block.
+    held: [Paddr; NR_FRAMES_PER_PAGEBLOCK],
+    nr_held: usize,
+}
+
+static EVACUATION: SpinLock<Option<Evacuation>, LocalIrqDisabled> = SpinLock::new(None);
+
+/// Allocates a frame as a migration target.
+fn alloc_target() -> Option<Paddr> {
+    let layout = Layout::from_size_align(PAGE_SIZE, PAGE_SIZE).unwrap();
+    let allocator = get_global_frame_allocator();
+
+    let mut evacuation = EVACUATION.lock();
+    loop {
+        let paddr = allocator.alloc_movable(layout)?;
+        match evacuation.as_mut() {
+            Some(evacuation) if evacuation.block.contains(&paddr) => {
+                evacuation.held[evacuation.nr_held] = paddr;
+                evacuation.nr_held += 1;
+            }
+            _ => return Some(paddr),
+        }
+    }
+}
@@ -97,7 +97,9 @@ pub(in crate::mm) struct MetaSlot {
     ///  - `0`: The page is being constructed ([`Frame::from_unused`])
     ///    or destructured ([`drop_last_in_place`]).
     ///  - `1..REF_COUNT_MAX`: The page is in use.
-    ///  - `REF_COUNT_MAX..REF_COUNT_UNIQUE`: Illegal values to
+    ///  - `REF_COUNT_FROZEN`: The page is being migrated. The references
+    ///    are frozen, so no new references can be created.
+    ///  - `REF_COUNT_MAX..REF_COUNT_FROZEN`: Illegal values to
     ///    prevent the reference count from overflowing. Otherwise,
     ///    overflowing the reference count will cause soundness issue.
     ///
@@ -123,6 +125,7 @@ pub(in crate::mm) struct MetaSlot {
 
 pub(super) const REF_COUNT_UNUSED: u64 = u64::MAX;
 pub(super) const REF_COUNT_UNIQUE: u64 = u64::MAX - 1;
+pub(super) const REF_COUNT_FROZEN: u64 = u64::MAX - 2;
 pub(super) const REF_COUNT_MAX: u64 = i64::MAX as u64;
 
 type FrameMetaVtablePtr = core::ptr::DynMetadata<dyn AnyFrameMeta>;
@@ -273,7 +276,7 @@ impl MetaSlot {
             match slot.ref_count.load(Ordering::Relaxed) {
                 REF_COUNT_UNUSED => return Err(GetFrameError::Unused),
                 REF_COUNT_UNIQUE => return Err(GetFrameError::Unique),
-                0 => return Err(GetFrameError::Busy),
+                0 | REF_COUNT_FROZEN => return Err(GetFrameError::Busy),
                 last_ref_cnt => {
                     if last_ref_cnt >= REF_COUNT_MAX {
                         // See `Self::inc_ref_count` for the explanation.
@@ -319,6 +322,68 @@ impl MetaSlot {
         }
     }
 
+    /// Tries increasing the frame reference count by one.
+    ///
+    /// It fails if the reference count is frozen, i.e., the frame is being
+    /// migrated.
+    ///
+    /// # Safety
+    ///
+    /// The caller must have already held a reference to the frame, or the
+    /// frame must be protected from being dropped in other ways (e.g., by
+    /// RCU).
+    pub(super) unsafe fn try_inc_ref_count(&self) -> bool {
+        let mut last_ref_cnt = self.ref_count.load(Ordering::Relaxed);
+        loop {
+            if last_ref_cnt == REF_COUNT_FROZEN {
+                return false;
+            }
+            debug_assert!(last_ref_cnt != 0 && last_ref_cnt != REF_COUNT_UNUSED);
+            if last_ref_cnt >= REF_COUNT_MAX {
+                // See `Self::inc_ref_count` for the explanation.
+                abort();
+            }
+            // Using `Acquire` here to pair with `unfreeze_ref_count`. It
+            // ensures that the writes of the migration are visible to us.
+            match self.ref_count.compare_exchange_weak(
+                last_ref_cnt,
+                last_ref_cnt + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(ref_cnt) => last_ref_cnt = ref_cnt,
+            }
+        }
+    }
+
+    /// Freezes the frame reference count if it equals to `expected`.
+    ///
+    /// Returns whether the reference count is frozen. A frozen reference
+    /// count must be unfrozen with [`Self::unfreeze_ref_count`].
+    pub(super) fn freeze_ref_count(&self, expected: u64) -> bool {
+        debug_assert!(expected != 0 && expected < REF_COUNT_MAX);
+        self.ref_count
+            .compare_exchange(
+                expected,
+                REF_COUNT_FROZEN,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            )
+            .is_ok()
+    }
+
+    /// Unfreezes the frame reference count and restores it to `ref_cnt`.
+    ///
+    /// # Safety
+    ///
+    /// The reference count must be frozen by the caller with
+    /// [`Self::freeze_ref_count`], where `ref_cnt` is the expected value.
+    pub(super) unsafe fn unfreeze_ref_count(&self, ref_cnt: u64) {
+        debug_assert_eq!(self.ref_count.load(Ordering::Relaxed), REF_COUNT_FROZEN);
+        self.ref_count.store(ref_cnt, Ordering::Release);
+    }
+
     /// Gets the corresponding frame's physical address.
     pub(super) fn frame_paddr(&self) -> Paddr {
         mapping::meta_to_frame::<PagingConsts>(self as *const MetaSlot as Vaddr)
@@ -32,9 +32,11 @@
 //! can create custom metadata types by implementing the [`AnyFrameMeta`] trait.
 
 pub mod allocator;
+pub mod compaction;
 pub mod linked_list;
 pub mod meta;
 pub mod segment;
+pub mod shrinker;
 pub mod unique;
 pub mod untyped;
 
@@ -51,7 +53,7 @@ use core::{
 };
 
 pub use allocator::GlobalFrameAllocator;
-use meta::{AnyFrameMeta, GetFrameError, MetaSlot, REF_COUNT_UNUSED, mapping};
+use meta::{AnyFrameMeta, GetFrameError, MetaSlot, REF_COUNT_FROZEN, REF_COUNT_UNUSED, mapping};
 pub use segment::Segment;
 use untyped::{AnyUFrameMeta, UFrame};
 
@@ -246,19 +248,72 @@ impl<M: AnyFrameMeta + ?Sized> Clone for Frame<M> {
     }
 }
 
-impl<M: AnyFrameMeta + ?Sized> Drop for Frame<M> {
-    fn drop(&mut self) {
+impl<M: AnyFrameMeta + ?Sized> Frame<M> {
+    /// Tries cloning the handle to the frame.
+    ///
+    /// It fails if the frame is being migrated (see [`compaction`]). Unlike
+    /// [`Clone::clone`], it can be used on a handle that is only protected by
+    /// RCU, e.g., a handle loaded from an RCU-protected data structure by a
+    /// lockless reader. In that case, the reader should load the handle again
+    /// after a successful clone, and retry if the loaded handle is changed,
+    /// because the frame may have been migrated before being cloned.
+    pub fn try_clone(&self) -> Option<Self> {
+        // SAFETY: We have already held a reference to the frame, or the frame
+        // is protected by RCU.
+        if !unsafe { self.slot().try_inc_ref_count() } {
+            return None;
+        }
+
+        Some(Self {
+            ptr: self.ptr,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Drops the handle without returning the frame to the frame allocator.
+    ///
+    /// It returns `true` if this is the last handle, in which case the caller
+    /// must return the frame to the frame allocator.
+    pub(in crate::mm) fn drop_without_dealloc(self) -> bool {
+        let this = ManuallyDrop::new(self);
+        // SAFETY: The handle is forgotten and never used again.
+        unsafe { this.release() }
+    }
+
+    /// Releases the reference held by the handle.
+    ///
+    /// It returns `true` if this is the last handle and the metadata is dropped.
+    ///
+    /// # Safety
+    ///
+    /// The handle must not be used or dropped afterwards.
+    unsafe fn release(&self) -> bool {
         let last_ref_cnt = self.slot().ref_count.fetch_sub(1, Ordering::Release);
-        debug_assert!(last_ref_cnt != 0 && last_ref_cnt != REF_COUNT_UNUSED);
+        debug_assert!(
+            last_ref_cnt != 0
+                && last_ref_cnt != REF_COUNT_UNUSED
+                && last_ref_cnt != REF_COUNT_FROZEN
+        );
+
+        if last_ref_cnt != 1 {
+            return false;
+        }
+
+        // A fence is needed here with the same reasons stated in the implementation of
+        // `Arc::drop`: <https://doc.rust-lang.org/std/sync/struct.Arc.html#method.drop>.
+        core::sync::atomic::fence(Ordering::Acquire);
 
-        if last_ref_cnt == 1 {
-            // A fence is needed here with the same reasons stated in the implementation of
-            // `Arc::drop`: <https://doc.rust-lang.org/std/sync/struct.Arc.html#method.drop>.
-            core::sync::atomic::fence(Ordering::Acquire);
+        // SAFETY: this is the last reference and is about to be dropped.
+        unsafe { self.slot().drop_last_in_place() };
 
-            // SAFETY: this is the last reference and is about to be dropped.
-            unsafe { self.slot().drop_last_in_place() };
+        true
+    }
+}
 
+impl<M: AnyFrameMeta + ?Sized> Drop for Frame<M> {
+    fn drop(&mut self) {
+        // SAFETY: The handle is being dropped.
+        if unsafe { self.release() } {
             allocator::get_global_frame_allocator().dealloc(self.paddr(), PAGE_SIZE);
         }
     }
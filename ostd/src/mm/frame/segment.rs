@@ -45,11 +45,12 @@ pub type USegment = Segment<dyn AnyUFrameMeta>;
 
 impl<M: AnyFrameMeta + ?Sized> Drop for Segment<M> {
     fn drop(&mut self) {
-        for paddr in self.range.clone().step_by(PAGE_SIZE) {
+        let frames = self.range.clone().step_by(PAGE_SIZE).map(|paddr| {
             // SAFETY: for each frame there would be a forgotten handle
             // when creating the `Segment` object.
-            drop(unsafe { Frame::<M>::from_raw(paddr) });
-        }
+            unsafe { Frame::<M>::from_raw(paddr) }
+        });
+        super::allocator::drop_frames(frames);
     }
 }
 
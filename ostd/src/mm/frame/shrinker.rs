@@ -0,0 +1,42 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Shrinkers that release cached memory under memory pressure.
+//!
+//! Kernel components that cache free memory (e.g., the slab caches of the
+//! heap) can register a [`Shrinker`]. When a frame allocation fails, OSTD
+//! calls the global heap allocator and all the registered shrinkers to
+//! release their cached memory, and then retries the allocation once.
+
+use crate::{
+    prelude::*,
+    sync::{LocalIrqDisabled, RwLock},
+};
+
+/// A component that can release its cached memory under memory pressure.
+pub trait Shrinker: Sync {
+    /// Releases the cached memory back to the frame allocator.
+    ///
+    /// It returns the number of frames released.
+    ///
+    /// The method is called in the context of the failed frame allocation,
+    /// which may be in atomic mode or even in the middle of the component's
+    /// own operations. So it must not sleep, must not block on the locks that
+    /// may be held when allocating frames, and must not allocate frames.
+    fn shrink(&self) -> usize;
+}
+
+static SHRINKERS: RwLock<Vec<&'static dyn Shrinker>, LocalIrqDisabled> = RwLock::new(Vec::new());
+
+/// Registers a shrinker that will be called under memory pressure.
+pub fn register_shrinker(shrinker: &'static dyn Shrinker) {
+    SHRINKERS.write().push(shrinker);
+}
+
+/// Calls all the shrinkers and returns the number of frames released.
+pub(super) fn shrink_all() -> usize {
+    let mut nr_released = crate::mm::heap::shrink();
+    for shrinker in SHRINKERS.read().iter() {
+        nr_released += shrinker.shrink();
+    }
+    nr_released
+}
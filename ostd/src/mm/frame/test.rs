@@ -88,6 +88,129 @@ mod frame {
         assert!(result.is_ok());
         assert_eq!(result.unwrap().meta().value, 42);
     }
+
+    #[ktest]
+    fn frames_bulk_alloc_drop() {
+        let frames = FrameAllocOptions::new()
+            .alloc_frames_with(100, |paddr| MockFrameMeta {
+                value: (paddr / PAGE_SIZE) as u32,
+            })
+            .expect("Failed to allocate frames in bulk");
+        assert_eq!(frames.len(), 100);
+
+        let mut paddrs = frames.iter().map(|frame| frame.paddr()).collect::<Vec<_>>();
+        for frame in frames.iter() {
+            assert_eq!(frame.meta().value, (frame.paddr() / PAGE_SIZE) as u32);
+            assert_eq!(frame.reference_count(), 1);
+        }
+        paddrs.sort_unstable();
+        paddrs.dedup();
+        assert_eq!(paddrs.len(), 100);
+
+        // Frames that are still referenced should not be deallocated.
+        let kept = frames[0].clone();
+        allocator::drop_frames(frames);
+        assert_eq!(kept.reference_count(), 1);
+        assert_eq!(kept.meta().value, (kept.paddr() / PAGE_SIZE) as u32);
+    }
+}
+
+// Frame migration tests
+mod compaction {
+    use align_ext::AlignExt;
+
+    use super::*;
+    use crate::mm::frame::{
+        compaction::{
+            FrameMigrator, PAGEBLOCK_SIZE, compact_memory, migrate_frame, register_frame_migrator,
+        },
+        unique::UniqueFrame,
+    };
+
+    /// The number of attempts to migrate frames with [`MockUFrameMeta`].
+    static NR_MIGRATION_ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+
+    struct MockMigrator;
+
+    impl FrameMigrator for MockMigrator {
+        fn migrate(&self, _frame: Frame<dyn AnyFrameMeta>) -> bool {
+            NR_MIGRATION_ATTEMPTS.fetch_add(1, Ordering::Relaxed);
+            false
+        }
+    }
+
+    #[ktest]
+    fn migrate_unshared_frame() {
+        let frame = FrameAllocOptions::new()
+            .alloc_frame_with(MockUFrameMeta { value: 1 })
+            .unwrap();
+        let data = [0xAA; 128];
+        frame.writer().write(&mut data.as_slice().into());
+
+        let mut migrated = None;
+        let is_migrated = migrate_frame(&frame, 1, MockUFrameMeta { value: 2 }, |new_frame| {
+            // No new references can be created during the migration.
+            assert!(frame.try_clone().is_none());
+            migrated = Some(new_frame);
+        });
+        assert!(is_migrated);
+        assert_eq!(frame.reference_count(), 1);
+
+        let migrated = migrated.unwrap();
+        assert_ne!(migrated.paddr(), frame.paddr());
+        assert_eq!(migrated.meta().value, 2);
+        let mut buffer = [0; 128];
+        migrated.reader().read(&mut buffer.as_mut_slice().into());
+        assert_eq!(buffer, data);
+    }
+
+    #[ktest]
+    fn migrate_shared_frame() {
+        let frame = FrameAllocOptions::new()
+            .alloc_frame_with(MockUFrameMeta { value: 1 })
+            .unwrap();
+        let shared = frame.try_clone().unwrap();
+
+        let is_migrated = migrate_frame(&frame, 1, MockUFrameMeta { value: 2 }, |_| {
+            unreachable!("a shared frame should not be migrated")
+        });
+        assert!(!is_migrated);
+        assert_eq!(frame.reference_count(), 2);
+        assert_eq!(shared.meta().value, 1);
+    }
+
+    #[ktest]
+    fn compact_block_with_unique_frame() {
+        register_frame_migrator::<MockUFrameMeta>(&MockMigrator);
+
+        // Two blocks of frames always cover a whole block.
+        let segment = FrameAllocOptions::new()
+            .alloc_segment_with(PAGEBLOCK_SIZE / PAGE_SIZE * 2, |_| {
+                MockUFrameMeta::default()
+            })
+            .unwrap();
+        let block_start = segment.paddr().align_up(PAGEBLOCK_SIZE);
+
+        // Keep a movable frame and a uniquely owned frame in the block, and
+        // free the others.
+        let mut movable = None;
+        let mut unique = None;
+        for frame in segment {
+            if frame.paddr() == block_start {
+                movable = Some(frame);
+            } else if frame.paddr() == block_start + PAGE_SIZE {
+                unique = Some(UniqueFrame::try_from(frame).unwrap());
+            }
+        }
+
+        // The block cannot be evacuated since the uniquely owned frame is
+        // not free and has no migrator.
+        compact_memory();
+        assert_eq!(NR_MIGRATION_ATTEMPTS.load(Ordering::Relaxed), 0);
+
+        drop(unique);
+        drop(movable);
+    }
 }
 
 // Frame linked list tests
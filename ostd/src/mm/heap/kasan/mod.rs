@@ -0,0 +1,493 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The kernel address sanitizer (KASAN) of the heap.
+//!
+//! The sanitizer is enabled by the `kasan` feature. It catches the out-of-bounds (OOB) and
+//! use-after-free (UAF) bugs of heap memory deterministically:
+//!
+//! - **Redzones.** An allocation is surrounded by redzones, which are filled with a known pattern.
+//!   The redzones are checked when the allocation is freed, so that the OOB writes are caught.
+//! - **Quarantine.** A freed allocation is poisoned and kept in a quarantine instead of being
+//!   reused immediately. The poison is checked when the allocation leaves the quarantine, so that
+//!   the UAF writes are caught. The UAF reads get the poison rather than the data of a new owner.
+//! - **Headers.** The left redzone begins with a header of the allocation, so that the double
+//!   frees and the frees with wrong layouts are caught.
+//!
+//! A new allocation is also filled with a poison, so that the reads of uninitialized memory are
+//! likely to cause visible errors.
+//!
+//! With the `kasan_shadow` feature, the state of every 8 bytes of the heap is also recorded in
+//! the shadow memory, and the kernel memory accessed via [`VmReader`] and [`VmWriter`] is checked
+//! against the shadow memory, so the bad accesses are caught when they happen. Safe Rust cannot
+//! access memory out of bounds or after free, so it is the unsafe code, which accesses memory via
+//! raw pointers, that needs to be checked.
+//!
+//! Any bug that is found aborts the kernel with a report.
+//!
+//! Only the heap is covered. The stack memory is not checked, since the stack variables are
+//! placed by the compiler, and guarding them needs the compiler instrumentation that inserts
+//! redzones between the variables.
+//!
+//! [`VmReader`]: crate::mm::VmReader
+//! [`VmWriter`]: crate::mm::VmWriter
+
+#[cfg(feature = "kasan_shadow")]
+mod shadow;
+
+use core::{alloc::Layout, ptr};
+
+#[cfg(feature = "kasan_shadow")]
+pub(crate) use self::shadow::{AccessKind, check_access, init as init_shadow};
+use super::{alloc_slot, dealloc_slot, slot_size_from_layout};
+use crate::sync::{LocalIrqDisabled, SpinLock};
+
+/// The minimum size of the left redzone, which includes the [`Header`].
+const LEFT_REDZONE_SIZE: usize = 64;
+/// The minimum size of the right redzone.
+///
+/// The right redzone also takes the rest of the slot.
+const MIN_RIGHT_REDZONE_SIZE: usize = 16;
+
+/// The byte that fills the redzones.
+const REDZONE_BYTE: u8 = 0xbb;
+/// The byte that fills the new allocations.
+const ALLOC_POISON_BYTE: u8 = 0x5a;
+/// The byte that fills the freed allocations.
+const FREE_POISON_BYTE: u8 = 0x6b;
+
+/// The maximum total size of the slots in the quarantine.
+const QUARANTINE_MAX_BYTES: usize = 4 * 1024 * 1024;
+
+const MAGIC_ALLOCATED: u64 = 0x6b61_7361_6e5f_616c;
+const MAGIC_FREED: u64 = 0x6b61_7361_6e5f_6672;
+
+/// The header of an allocation at the beginning of its slot.
+#[repr(C)]
+struct Header {
+    magic: u64,
+    /// The layout requested by the user.
+    size: usize,
+    align: usize,
+    /// The next allocation in the quarantine.
+    next: *mut Header,
+}
+
+/// The placement of an allocation in its slot.
+#[derive(Clone, Copy)]
+struct Placement {
+    /// The layout requested by the user.
+    layout: Layout,
+    /// The layout of the slot, which holds the allocation and its redzones.
+    slot_layout: Layout,
+    /// The size of the slot, which may be larger than the size of the slot layout.
+    slot_size: usize,
+    /// The offset of the allocation in the slot.
+    offset: usize,
+}
+
+impl Placement {
+    fn new(layout: Layout) -> Option<Self> {
+        let offset = layout.align().max(LEFT_REDZONE_SIZE);
+        let slot_layout = Layout::from_size_align(
+            offset
+                .checked_add(layout.size())?
+                .checked_add(MIN_RIGHT_REDZONE_SIZE)?,
+            layout.align().max(align_of::<Header>()),
+        )
+        .ok()?;
+        let slot_size = slot_size_from_layout(slot_layout)?.size();
+
+        Some(Self {
+            layout,
+            slot_layout,
+            slot_size,
+            offset,
+        })
+    }
+
+    /// Returns the range of the right redzone in the slot.
+    fn right_redzone(&self) -> (usize, usize) {
+        let start = self.offset + self.layout.size();
+        (start, self.slot_size - start)
+    }
+}
+
+/// Allocates memory for the layout with the redzones around it.
+pub(super) fn alloc(layout: Layout) -> *mut u8 {
+    let Some(placement) = Placement::new(layout) else {
+        return ptr::null_mut();
+    };
+
+    let slot = alloc_slot(placement.slot_layout);
+    if slot.is_null() {
+        return slot;
+    }
+
+    let header_size = size_of::<Header>();
+    let (right_start, right_size) = placement.right_redzone();
+    // SAFETY: The slot is valid for writes of `slot_size` bytes, and it is aligned for `Header`.
+    let ptr = unsafe {
+        slot.cast::<Header>().write(Header {
+            magic: MAGIC_ALLOCATED,
+            size: layout.size(),
+            align: layout.align(),
+            next: ptr::null_mut(),
+        });
+        slot.add(header_size)
+            .write_bytes(REDZONE_BYTE, placement.offset - header_size);
+        slot.add(right_start).write_bytes(REDZONE_BYTE, right_size);
+
+        let ptr = slot.add(placement.offset);
+        ptr.write_bytes(ALLOC_POISON_BYTE, layout.size());
+        ptr
+    };
+
+    #[cfg(feature = "kasan_shadow")]
+    shadow::mark_allocated(slot.addr(), &placement);
+
+    ptr
+}
+
+/// Checks and poisons the freed memory and puts it into the quarantine.
+///
+/// # Safety
+///
+/// The pointer must be allocated by [`alloc`] with the same layout.
+pub(super) unsafe fn dealloc(ptr: *mut u8, layout: Layout) {
+    let Some(placement) = Placement::new(layout) else {
+        report_bad_free(ptr, layout, "the layout is invalid");
+    };
+
+    let slot = ptr.wrapping_sub(placement.offset);
+    let header = slot.cast::<Header>();
+    // SAFETY: If the pointer is allocated by `alloc` as required, the header is valid. Otherwise,
+    // the header is still likely to be in some heap slot, and the bug is reported below.
+    if let Err(bad_free) = unsafe { check_header(header, layout) } {
+        report_bad_free(ptr, layout, bad_free.reason());
+    }
+
+    // SAFETY: The slot is allocated with the placement.
+    if let Some(offset) = unsafe { check_redzones(slot, &placement) } {
+        report_corruption(ptr, layout, offset, "out-of-bounds write");
+    }
+
+    // SAFETY: The memory is allocated and can be written until it is deallocated.
+    unsafe {
+        ptr.write_bytes(FREE_POISON_BYTE, layout.size());
+        (*header).magic = MAGIC_FREED;
+    }
+
+    #[cfg(feature = "kasan_shadow")]
+    shadow::mark_freed(ptr.addr(), layout.size());
+
+    let evicted = QUARANTINE.lock().put(header, placement.slot_size);
+
+    let mut next = evicted;
+    while !next.is_null() {
+        let header = next;
+        // SAFETY: The evicted allocations have been taken out of the quarantine, so we own them.
+        unsafe {
+            next = (*header).next;
+            release(header);
+        }
+    }
+}
+
+/// Checks the poison and redzones of an allocation leaving the quarantine, and deallocates it.
+///
+/// # Safety
+///
+/// The header must belong to an allocation that is freed by [`dealloc`] and is not in the
+/// quarantine.
+unsafe fn release(header: *mut Header) {
+    // SAFETY: The header is valid as required by the caller.
+    let (size, align) = unsafe { ((*header).size, (*header).align) };
+    // SAFETY: The layout is valid since it is used to allocate the memory.
+    let layout = unsafe { Layout::from_size_align_unchecked(size, align) };
+    let placement = Placement::new(layout).unwrap();
+    let slot = header.cast::<u8>();
+
+    // SAFETY: The slot is valid for reads of `slot_size` bytes.
+    let ptr = unsafe { slot.add(placement.offset) };
+    // SAFETY: The memory is freed but not deallocated yet.
+    if let Some(offset) = unsafe { find_byte_mismatch(ptr, size, FREE_POISON_BYTE) } {
+        report_corruption(ptr, layout, offset, "use-after-free write");
+    }
+    // SAFETY: The slot is allocated with the placement.
+    if let Some(offset) = unsafe { check_redzones(slot, &placement) } {
+        report_corruption(ptr, layout, offset, "out-of-bounds write");
+    }
+
+    #[cfg(feature = "kasan_shadow")]
+    shadow::clear(slot.addr(), placement.slot_size);
+
+    // SAFETY: The slot is allocated by `alloc_slot` with the slot layout.
+    unsafe { dealloc_slot(slot, placement.slot_layout) };
+}
+
+/// The reasons why freeing memory is invalid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BadFree {
+    SizeMismatch,
+    DoubleFree,
+    NotFromHeap,
+}
+
+impl BadFree {
+    fn reason(self) -> &'static str {
+        match self {
+            Self::SizeMismatch => "the size does not match the allocation",
+            Self::DoubleFree => "the memory has been freed (double free)",
+            Self::NotFromHeap => "the memory is not allocated from the heap",
+        }
+    }
+}
+
+/// Checks whether the allocation with the header can be freed with the layout.
+///
+/// # Safety
+///
+/// The header must be valid for reads.
+unsafe fn check_header(header: *const Header, layout: Layout) -> Result<(), BadFree> {
+    // SAFETY: The header is valid for reads as required by the caller.
+    let (magic, size) = unsafe { ((*header).magic, (*header).size) };
+    match magic {
+        MAGIC_ALLOCATED if size == layout.size() => Ok(()),
+        MAGIC_ALLOCATED => Err(BadFree::SizeMismatch),
+        MAGIC_FREED => Err(BadFree::DoubleFree),
+        _ => Err(BadFree::NotFromHeap),
+    }
+}
+
+/// Checks whether the redzones of an allocation are intact.
+///
+/// Returns the offset of the first corrupted byte relative to the allocation, if any.
+///
+/// # Safety
+///
+/// The slot must be allocated by [`alloc`] with the placement and not be deallocated yet.
+unsafe fn check_redzones(slot: *mut u8, placement: &Placement) -> Option<isize> {
+    let header_size = size_of::<Header>();
+
+    let (left_start, left_size) = (header_size, placement.offset - header_size);
+    // SAFETY: The left redzone is in the slot.
+    if let Some(offset) =
+        unsafe { find_byte_mismatch(slot.add(left_start), left_size, REDZONE_BYTE) }
+    {
+        return Some((left_start + offset) as isize - placement.offset as isize);
+    }
+
+    let (right_start, right_size) = placement.right_redzone();
+    // SAFETY: The right redzone is in the slot.
+    if let Some(offset) =
+        unsafe { find_byte_mismatch(slot.add(right_start), right_size, REDZONE_BYTE) }
+    {
+        return Some((right_start + offset - placement.offset) as isize);
+    }
+
+    None
+}
+
+/// Returns the offset of the first byte that is not `byte`.
+///
+/// # Safety
+///
+/// The memory must be valid for reads of `len` bytes.
+unsafe fn find_byte_mismatch(ptr: *const u8, len: usize, byte: u8) -> Option<usize> {
+    // SAFETY: The memory is valid for reads as required by the caller.
+    let bytes = unsafe { core::slice::from_raw_parts(ptr, len) };
+    bytes.iter().position(|b| *b != byte)
+}
+
+/// The freed allocations that are not deallocated yet.
+///
+/// The allocations are linked via their headers from the oldest to the newest.
+struct Quarantine {
+    head: *mut Header,
+    tail: *mut Header,
+    nr_bytes: usize,
+}
+
+// SAFETY: The allocations in the quarantine are owned by the quarantine, which can be accessed
+// from any CPU.
+unsafe impl Send for Quarantine {}
+
+static QUARANTINE: SpinLock<Quarantine, LocalIrqDisabled> = SpinLock::new(Quarantine {
+    head: ptr::null_mut(),
+    tail: ptr::null_mut(),
+    nr_bytes: 0,
+});
+
+impl Quarantine {
+    /// Puts a freed allocation into the quarantine.
+    ///
+    /// Returns the list of the oldest allocations that are evicted to keep the quarantine within
+    /// [`QUARANTINE_MAX_BYTES`].
+    fn put(&mut self, header: *mut Header, slot_size: usize) -> *mut Header {
+        if self.tail.is_null() {
+            self.head = header;
+        } else {
+            // SAFETY: The allocations in the quarantine are owned by the quarantine.
+            unsafe { (*self.tail).next = header };
+        }
+        self.tail = header;
+        self.nr_bytes += slot_size;
+
+        let evicted = self.head;
+        let mut last_evicted = ptr::null_mut();
+        while self.nr_bytes > QUARANTINE_MAX_BYTES {
+            last_evicted = self.head;
+            // SAFETY: The allocations in the quarantine are owned by the quarantine, and their
+            // layouts are valid since they are used to allocate the memory.
+            unsafe {
+                let layout =
+                    Layout::from_size_align_unchecked((*self.head).size, (*self.head).align);
+                self.nr_bytes -= Placement::new(layout).unwrap().slot_size;
+                self.head = (*self.head).next;
+            }
+        }
+
+        if last_evicted.is_null() {
+            return ptr::null_mut();
+        }
+        // SAFETY: The evicted allocations are detached from the quarantine.
+        unsafe { (*last_evicted).next = ptr::null_mut() };
+        if self.head.is_null() {
+            self.tail = ptr::null_mut();
+        }
+        evicted
+    }
+}
+
+fn report_bad_free(ptr: *mut u8, layout: Layout, reason: &str) -> ! {
+    log::error!("KASAN: invalid free of {:p} with layout {:?}", ptr, layout);
+    log::error!("KASAN: {}", reason);
+    abort()
+}
+
+fn report_corruption(ptr: *mut u8, layout: Layout, offset: isize, kind: &str) -> ! {
+    log::error!(
+        "KASAN: {} at offset {} of the allocation at {:p} with layout {:?}",
+        kind,
+        offset,
+        ptr,
+        layout,
+    );
+    abort()
+}
+
+fn abort() -> ! {
+    crate::panic::print_stack_trace();
+    crate::panic::abort();
+}
+
+#[cfg(ktest)]
+mod test {
+    use super::*;
+    use crate::prelude::*;
+
+    #[ktest]
+    fn redzones_and_poison() {
+        let layout = Layout::from_size_align(13, 1).unwrap();
+        let placement = Placement::new(layout).unwrap();
+        let ptr = alloc(layout);
+        assert!(!ptr.is_null());
+
+        // SAFETY: The redzones and the memory are in the slot allocated above.
+        unsafe {
+            assert_eq!(find_byte_mismatch(ptr, 13, ALLOC_POISON_BYTE), None);
+            let (right_start, right_size) = placement.right_redzone();
+            let right_redzone = ptr.sub(placement.offset).add(right_start);
+            assert_eq!(
+                find_byte_mismatch(right_redzone, right_size, REDZONE_BYTE),
+                None
+            );
+
+            dealloc(ptr, layout);
+        }
+    }
+
+    #[ktest]
+    fn out_of_bounds_write() {
+        let layout = Layout::from_size_align(13, 1).unwrap();
+        let placement = Placement::new(layout).unwrap();
+        let ptr = alloc(layout);
+        assert!(!ptr.is_null());
+        let slot = ptr.wrapping_sub(placement.offset);
+
+        // SAFETY: The redzones are in the slot allocated above, and they are restored before the
+        // memory is freed.
+        unsafe {
+            assert_eq!(check_redzones(slot, &placement), None);
+
+            ptr.add(13).write(0);
+            assert_eq!(check_redzones(slot, &placement), Some(13));
+            ptr.add(13).write(REDZONE_BYTE);
+
+            ptr.sub(1).write(0);
+            assert_eq!(check_redzones(slot, &placement), Some(-1));
+            ptr.sub(1).write(REDZONE_BYTE);
+
+            assert_eq!(check_redzones(slot, &placement), None);
+            dealloc(ptr, layout);
+        }
+    }
+
+    #[ktest]
+    fn use_after_free_write() {
+        let layout = Layout::from_size_align(32, 8).unwrap();
+        let ptr = alloc(layout);
+        assert!(!ptr.is_null());
+
+        // SAFETY: The memory is allocated above. It is poisoned as if it were freed, so that the
+        // allocation is not put into the quarantine while it is corrupted.
+        unsafe {
+            ptr.write_bytes(FREE_POISON_BYTE, 32);
+            assert_eq!(find_byte_mismatch(ptr, 32, FREE_POISON_BYTE), None);
+
+            ptr.add(5).write(0x42);
+            assert_eq!(find_byte_mismatch(ptr, 32, FREE_POISON_BYTE), Some(5));
+
+            dealloc(ptr, layout);
+        }
+    }
+
+    #[ktest]
+    fn bad_free() {
+        let layout = Layout::from_size_align(24, 8).unwrap();
+        let placement = Placement::new(layout).unwrap();
+        let ptr = alloc(layout);
+        assert!(!ptr.is_null());
+        let header = ptr.wrapping_sub(placement.offset).cast::<Header>();
+        let wrong_layout = Layout::from_size_align(25, 8).unwrap();
+
+        // SAFETY: The header is in the slot allocated above, and it is restored before the memory
+        // is freed.
+        unsafe {
+            assert_eq!(check_header(header, layout), Ok(()));
+            assert_eq!(
+                check_header(header, wrong_layout),
+                Err(BadFree::SizeMismatch)
+            );
+
+            (*header).magic = MAGIC_FREED;
+            assert_eq!(check_header(header, layout), Err(BadFree::DoubleFree));
+
+            (*header).magic = 0;
+            assert_eq!(check_header(header, layout), Err(BadFree::NotFromHeap));
+
+            (*header).magic = MAGIC_ALLOCATED;
+            dealloc(ptr, layout);
+        }
+    }
+
+    #[ktest]
+    fn large_alignment() {
+        #[repr(align(4096))]
+        struct PageAligned([u8; 16]);
+
+        let boxed = Box::new(PageAligned([0u8; 16]));
+        assert!((&raw const *boxed).addr().is_multiple_of(4096));
+        assert_eq!(boxed.0, [0u8; 16]);
+    }
+}
@@ -0,0 +1,170 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The shadow memory of the heap.
+//!
+//! Every 8 bytes (a _granule_) of the memory in the linear mapping is described by one byte of
+//! the shadow memory:
+//!
+//! - `0` means that all the bytes in the granule are accessible;
+//! - `1..=7` means that only the first bytes of that number in the granule are accessible;
+//! - [`REDZONE`] and [`FREED`] mean that no bytes in the granule are accessible.
+//!
+//! The memory that is not in the heap is always accessible.
+//!
+//! The shadow memory takes one eighth of the physical memory, and is allocated in chunks so that
+//! no large contiguous memory is needed. The heap allocations before the shadow memory is
+//! allocated are not tracked.
+
+use alloc::boxed::Box;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use align_ext::AlignExt;
+use spin::Once;
+
+use super::Placement;
+use crate::mm::{
+    FrameAllocOptions, PAGE_SIZE, Vaddr, frame::max_paddr, kspace::LINEAR_MAPPING_BASE_VADDR,
+    paddr_to_vaddr,
+};
+
+const GRANULE_SIZE: usize = 8;
+
+/// The shadow value of a redzone granule.
+const REDZONE: u8 = 0xfa;
+/// The shadow value of a freed granule.
+const FREED: u8 = 0xfd;
+
+/// The size of the memory that is described by a chunk of the shadow memory.
+const CHUNK_COVERAGE: usize = 16 * 1024 * 1024;
+const CHUNK_NR_FRAMES: usize = CHUNK_COVERAGE / GRANULE_SIZE / PAGE_SIZE;
+
+/// The virtual addresses of the chunks of the shadow memory.
+static CHUNKS: Once<Box<[Vaddr]>> = Once::new();
+
+/// Allocates the shadow memory.
+pub(crate) fn init() {
+    let nr_chunks = max_paddr().div_ceil(CHUNK_COVERAGE);
+    let chunks = (0..nr_chunks)
+        .map(|_| {
+            let segment = FrameAllocOptions::new()
+                .alloc_segment(CHUNK_NR_FRAMES)
+                .expect("no memory for the KASAN shadow memory");
+            // The shadow memory is never freed.
+            paddr_to_vaddr(segment.into_raw().start)
+        })
+        .collect();
+    CHUNKS.call_once(|| chunks);
+}
+
+/// Returns the shadow byte of the granule at the address.
+///
+/// Returns `None` if the address is not in the linear mapping or the shadow memory is not
+/// allocated yet.
+fn shadow_of(addr: Vaddr) -> Option<&'static AtomicU8> {
+    let offset = addr.checked_sub(LINEAR_MAPPING_BASE_VADDR)?;
+    let chunk = CHUNKS.get()?.get(offset / CHUNK_COVERAGE)?;
+    let ptr = (chunk + offset % CHUNK_COVERAGE / GRANULE_SIZE) as *const AtomicU8;
+    // SAFETY: The chunks of the shadow memory are zeroed on allocation and never freed. The
+    // shadow bytes are only accessed atomically.
+    Some(unsafe { &*ptr })
+}
+
+/// Sets the shadow bytes of the granules that overlap with the memory range.
+fn set(start: Vaddr, len: usize, value: u8) {
+    let end = start + len;
+    for granule in (start..end).step_by(GRANULE_SIZE) {
+        let Some(shadow) = shadow_of(granule) else {
+            return;
+        };
+        shadow.store(value, Ordering::Relaxed);
+    }
+}
+
+/// Marks the allocation accessible and its redzones inaccessible.
+pub(super) fn mark_allocated(slot: Vaddr, placement: &Placement) {
+    let ptr = slot + placement.offset;
+    let size = placement.layout.size();
+
+    // The slot and the offset are aligned to granules.
+    set(slot, placement.offset, REDZONE);
+    set(ptr, size, 0);
+    if size % GRANULE_SIZE != 0
+        && let Some(shadow) = shadow_of(ptr + size.align_down(GRANULE_SIZE))
+    {
+        shadow.store((size % GRANULE_SIZE) as u8, Ordering::Relaxed);
+    }
+
+    let right_start = (ptr + size).next_multiple_of(GRANULE_SIZE);
+    set(
+        right_start,
+        slot + placement.slot_size - right_start,
+        REDZONE,
+    );
+}
+
+/// Marks the freed allocation inaccessible.
+pub(super) fn mark_freed(ptr: Vaddr, size: usize) {
+    set(ptr, size, FREED);
+}
+
+/// Marks the slot accessible when it is deallocated.
+///
+/// The memory will be reused as other heap slots or frames.
+pub(super) fn clear(slot: Vaddr, slot_size: usize) {
+    set(slot, slot_size, 0);
+}
+
+/// The kind of a memory access.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum AccessKind {
+    Read,
+    Write,
+}
+
+/// Checks whether the memory range can be accessed, and aborts the kernel if it cannot.
+pub(crate) fn check_access(addr: Vaddr, len: usize, kind: AccessKind) {
+    if len == 0 || CHUNKS.get().is_none() {
+        return;
+    }
+
+    let end = addr.saturating_add(len);
+    let mut granule = addr.align_down(GRANULE_SIZE);
+    while granule < end {
+        let Some(shadow) = shadow_of(granule) else {
+            return;
+        };
+
+        let value = shadow.load(Ordering::Relaxed);
+        let nr_accessible = match value {
+            0 => GRANULE_SIZE,
+            1..=7 => value as usize,
+            _ => 0,
+        };
+        if end.min(granule + GRANULE_SIZE) > granule + nr_accessible {
+            let bad_addr = addr.max(granule + nr_accessible);
+            report_bad_access(bad_addr, addr, len, kind, value);
+        }
+
+        granule += GRANULE_SIZE;
+    }
+}
+
+fn report_bad_access(bad_addr: Vaddr, addr: Vaddr, len: usize, kind: AccessKind, value: u8) -> ! {
+    let bug = match value {
+        FREED => "use-after-free",
+        _ => "out-of-bounds",
+    };
+    log::error!(
+        "KASAN: {} {} of {} bytes at {:#x}, the first bad byte is at {:#x} (shadow {:#x})",
+        bug,
+        match kind {
+            AccessKind::Read => "read",
+            AccessKind::Write => "write",
+        },
+        len,
+        addr,
+        bad_addr,
+        value,
+    );
+    super::abort()
+}
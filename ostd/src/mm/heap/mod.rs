@@ -9,6 +9,8 @@ use core::{
 
 use crate::mm::Vaddr;
 
+#[cfg(feature = "kasan")]
+pub(crate) mod kasan;
 mod slab;
 mod slot;
 mod slot_list;
@@ -49,6 +51,18 @@ pub trait GlobalHeapAllocator: Sync {
     /// Each deallocation must correspond to exactly one previous allocation. The provided
     /// [`HeapSlot`] must match the one returned from the original allocation.
     fn dealloc(&self, slot: HeapSlot) -> Result<(), AllocError>;
+
+    /// Releases the memory cached by the allocator back to the frame allocator.
+    ///
+    /// OSTD calls this method under memory pressure, i.e., when a frame
+    /// allocation fails. It returns the number of frames released.
+    ///
+    /// This method may be called when the allocator is in the middle of an
+    /// allocation on the current CPU, so it must not block on the internal
+    /// locks of the allocator. It must not allocate frames either.
+    fn shrink(&self) -> usize {
+        0
+    }
 }
 
 unsafe extern "Rust" {
@@ -67,6 +81,13 @@ fn get_global_heap_allocator() -> &'static dyn GlobalHeapAllocator {
     unsafe { __GLOBAL_HEAP_ALLOCATOR_REF }
 }
 
+/// Releases the memory cached by the global heap allocator.
+///
+/// See [`GlobalHeapAllocator::shrink`].
+pub(in crate::mm) fn shrink() -> usize {
+    get_global_heap_allocator().shrink()
+}
+
 /// Gets the size and type of heap slots to serve allocations of the layout.
 ///
 /// This function is defined by the OSTD user and should be idempotent, as we
@@ -99,53 +120,81 @@ struct AllocDispatch;
 // Panicking should be fine, but we shouldn't unwind on panics.
 unsafe impl GlobalAlloc for AllocDispatch {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        let Some(required_slot) = slot_size_from_layout(layout) else {
-            abort_with_message!("Heap allocation size not found for layout = {:#x?}", layout);
-        };
-
-        let res = get_global_heap_allocator().alloc(layout);
-        let Ok(slot) = res else {
-            return core::ptr::null_mut();
-        };
-
-        if required_slot.size() != slot.size()
-            || slot.size() < layout.size()
-            || !(slot.as_ptr() as Vaddr).is_multiple_of(layout.align())
-        {
-            abort_with_message!(
-                "Heap allocation mismatch: slot ptr = {:p}, size = {:x}; layout = {:#x?}; required_slot = {:#x?}",
-                slot.as_ptr(),
-                slot.size(),
-                layout,
-                required_slot,
-            );
-        }
+        // The sanitizer surrounds the allocations with redzones in larger slots.
+        #[cfg(feature = "kasan")]
+        let ptr = kasan::alloc(layout);
+        #[cfg(not(feature = "kasan"))]
+        let ptr = alloc_slot(layout);
 
-        slot.as_ptr()
+        ptr
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
-        // Now we restore the `HeapSlot` from the pointer and the layout.
-        let Some(required_slot) = slot_size_from_layout(layout) else {
-            abort_with_message!(
-                "Heap deallocation size not found for layout = {:#x?}",
-                layout
-            );
-        };
-
-        // SAFETY: The validity of the pointer is guaranteed by the caller. The
-        // size must match the size of the slot when it was allocated, since we
-        // require `slot_size_from_layout` to be idempotent.
-        let slot = unsafe { HeapSlot::new(NonNull::new_unchecked(ptr), required_slot) };
-        let res = get_global_heap_allocator().dealloc(slot);
-
-        if res.is_err() {
-            abort_with_message!(
-                "Heap deallocation error, ptr = {:p}, layout = {:#x?}, required_slot = {:#x?}",
-                ptr,
-                layout,
-                required_slot,
-            );
+        // SAFETY: The safety is upheld by the caller.
+        unsafe {
+            #[cfg(feature = "kasan")]
+            kasan::dealloc(ptr, layout);
+            #[cfg(not(feature = "kasan"))]
+            dealloc_slot(ptr, layout);
         }
     }
 }
+
+/// Allocates a heap slot for the layout from the global heap allocator.
+///
+/// Returns a null pointer if the allocation fails.
+fn alloc_slot(layout: Layout) -> *mut u8 {
+    let Some(required_slot) = slot_size_from_layout(layout) else {
+        abort_with_message!("Heap allocation size not found for layout = {:#x?}", layout);
+    };
+
+    let res = get_global_heap_allocator().alloc(layout);
+    let Ok(slot) = res else {
+        return core::ptr::null_mut();
+    };
+
+    if required_slot.size() != slot.size()
+        || slot.size() < layout.size()
+        || !(slot.as_ptr() as Vaddr).is_multiple_of(layout.align())
+    {
+        abort_with_message!(
+            "Heap allocation mismatch: slot ptr = {:p}, size = {:x}; layout = {:#x?}; required_slot = {:#x?}",
+            slot.as_ptr(),
+            slot.size(),
+            layout,
+            required_slot,
+        );
+    }
+
+    slot.as_ptr()
+}
+
+/// Deallocates the heap slot allocated by [`alloc_slot`] to the global heap allocator.
+///
+/// # Safety
+///
+/// The pointer must be allocated by [`alloc_slot`] with the same layout.
+unsafe fn dealloc_slot(ptr: *mut u8, layout: Layout) {
+    // Now we restore the `HeapSlot` from the pointer and the layout.
+    let Some(required_slot) = slot_size_from_layout(layout) else {
+        abort_with_message!(
+            "Heap deallocation size not found for layout = {:#x?}",
+            layout
+        );
+    };
+
+    // SAFETY: The validity of the pointer is guaranteed by the caller. The
+    // size must match the size of the slot when it was allocated, since we
+    // require `slot_size_from_layout` to be idempotent.
+    let slot = unsafe { HeapSlot::new(NonNull::new_unchecked(ptr), required_slot) };
+    let res = get_global_heap_allocator().dealloc(slot);
+
+    if res.is_err() {
+        abort_with_message!(
+            "Heap deallocation error, ptr = {:p}, layout = {:#x?}, required_slot = {:#x?}",
+            ptr,
+            layout,
+            required_slot,
+        );
+    }
+}
@@ -86,6 +86,22 @@ impl<const SLOT_SIZE: usize> Slab<SLOT_SIZE> {
     /// If the size is less than `SLOT_SIZE` or [`PAGE_SIZE`], the size will be
     /// the maximum of the two.
     pub fn new() -> crate::prelude::Result<Self> {
+        Self::new_staggered(0)
+    }
+
+    /// Allocates a new slab whose first allocated slot is at the given offset.
+    ///
+    /// Slab caches can give successive slabs different offsets, so that the
+    /// objects allocated first from them are staggered over the CPU cache
+    /// sets. The offset is rounded down to `SLOT_SIZE` and wraps around at
+    /// [`PAGE_SIZE`].
+    ///
+    /// This only changes the order in which the slots are allocated. Unlike
+    /// the cache coloring of Linux, which shifts the slots into the unused
+    /// space at the end of a slab, a full slab always uses the same addresses.
+    /// The slots are aligned to their sizes, which are powers of two, so there
+    /// is no unused space to shift them into.
+    pub fn new_staggered(first_offset: usize) -> crate::prelude::Result<Self> {
         const { assert!(SLOT_SIZE <= PAGE_SIZE) };
         // To ensure we can store a pointer in each slot.
         const { assert!(SLOT_SIZE >= size_of::<usize>()) };
@@ -104,8 +120,11 @@ impl<const SLOT_SIZE: usize> Slab<SLOT_SIZE> {
         let head_paddr = slab.paddr();
         let head_vaddr = paddr_to_vaddr(head_paddr);
 
-        // Push each slot to the free list.
-        for slot_offset in (0..PAGE_SIZE).step_by(SLOT_SIZE) {
+        // Push each slot to the free list. The list is LIFO, so the slot at
+        // the first offset is pushed last.
+        let first_offset = first_offset % PAGE_SIZE / SLOT_SIZE * SLOT_SIZE;
+        for i in 1..=PAGE_SIZE / SLOT_SIZE {
+            let slot_offset = (first_offset + i * SLOT_SIZE) % PAGE_SIZE;
             // SAFETY: The slot is within the slab so it can't be NULL.
             let slot_ptr = unsafe { NonNull::new_unchecked((head_vaddr + slot_offset) as *mut u8) };
             // SAFETY: The slot is newly allocated in the slab.
@@ -37,6 +37,8 @@
 
 use super::{Fallible, Infallible};
 use crate::arch::mm::{__memcpy_fallible, __memset_fallible};
+#[cfg(feature = "kasan_shadow")]
+use crate::mm::heap::kasan::AccessKind;
 
 /// Copies `len` bytes from `src` to `dst`.
 ///
@@ -148,6 +150,12 @@ unsafe impl Memcpy<Infallible> for Infallible {
         // For more details and future possibilities, see
         // <https://github.com/asterinas/asterinas/pull/1001#discussion_r1667317406>.
 
+        #[cfg(feature = "kasan_shadow")]
+        {
+            crate::mm::heap::kasan::check_access(src.addr(), len, AccessKind::Read);
+            crate::mm::heap::kasan::check_access(dst.addr(), len, AccessKind::Write);
+        }
+
         // SAFETY: The safety is upheld by the caller.
         unsafe { core::intrinsics::volatile_copy_memory(dst, src, len) };
     }
@@ -159,6 +167,9 @@ unsafe impl Memcpy<Fallible> for Infallible {
     type Result = usize;
 
     unsafe fn memcpy(dst: *mut u8, src: *const u8, len: usize) -> usize {
+        #[cfg(feature = "kasan_shadow")]
+        crate::mm::heap::kasan::check_access(dst.addr(), len, AccessKind::Write);
+
         // SAFETY: The safety is upheld by the caller.
         let failed_bytes = unsafe { __memcpy_fallible(dst, src, len) };
         len - failed_bytes
@@ -171,6 +182,9 @@ unsafe impl Memcpy<Infallible> for Fallible {
     type Result = usize;
 
     unsafe fn memcpy(dst: *mut u8, src: *const u8, len: usize) -> usize {
+        #[cfg(feature = "kasan_shadow")]
+        crate::mm::heap::kasan::check_access(src.addr(), len, AccessKind::Read);
+
         // SAFETY: The safety is upheld by the caller.
         let failed_bytes = unsafe { __memcpy_fallible(dst, src, len) };
         len - failed_bytes
@@ -195,6 +209,9 @@ unsafe impl Memset for Infallible {
     type Result = ();
 
     unsafe fn memset(dst: *mut u8, value: u8, len: usize) {
+        #[cfg(feature = "kasan_shadow")]
+        crate::mm::heap::kasan::check_access(dst.addr(), len, AccessKind::Write);
+
         // SAFETY: The safety is upheld by the caller.
         unsafe { core::intrinsics::volatile_set_memory(dst, value, len) };
     }
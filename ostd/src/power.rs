@@ -2,9 +2,33 @@
 
 //! Power management.
 
+use alloc::{sync::Arc, vec::Vec};
+use core::{
+    convert::Infallible,
+    ops::Range,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
 use spin::Once;
 
-use crate::{arch::irq::disable_local_and_halt, cpu::CpuSet};
+#[cfg(target_arch = "x86_64")]
+use crate::arch::power::{
+    KexecJump, can_kexec as arch_can_kexec, can_suspend as arch_can_suspend,
+    suspend as arch_suspend,
+};
+use crate::{
+    Error, Result,
+    arch::irq::disable_local_and_halt,
+    boot::memory_region::MemoryRegionType,
+    cpu::{CpuSet, PinCurrentCpu},
+    io::IoMem,
+    irq::DisabledLocalIrqGuard,
+    mm::{
+        CachePolicy, FallibleVmWrite, FrameAllocOptions, HasPaddr, PAGE_SIZE, Paddr, PageFlags,
+        Segment, USegment, VmWriter, io::util::HasVmReaderWriter,
+    },
+    sync::SpinLock,
+};
 
 /// An exit code that denotes the reason for restarting or powering off.
 ///
@@ -82,6 +106,662 @@ pub fn poweroff(code: ExitCode) -> ! {
     machine_halt();
 }
 
+static SUSPEND_HANDLER: Once<fn()> = Once::new();
+
+/// Injects a handler that can put the system into the suspend-to-RAM state.
+///
+/// The handler should put the system into a sleeping state where the memory is kept powered
+/// (e.g., the ACPI S3 state). It never returns if it succeeds, because the CPU context is lost
+/// when the system sleeps. Instead, OSTD restores the CPU context after the system wakes up and
+/// returns from [`suspend`].
+///
+/// The function may be called only once; subsequent calls take no effect.
+pub fn inject_suspend_handler(handler: fn()) {
+    SUSPEND_HANDLER.call_once(|| handler);
+}
+
+/// Returns whether the system can be suspended to RAM.
+pub fn can_suspend() -> bool {
+    SUSPEND_HANDLER.is_completed() && arch_can_suspend()
+}
+
+/// Suspends the system to RAM and returns after the system wakes up.
+///
+/// The devices are suspended in the reverse order of their registration (see
+/// [`register_device_pm_ops`]) before the system sleeps, and are resumed in the order of their
+/// registration after the system wakes up.
+///
+/// The caller should make sure that no other tasks are running, e.g., by freezing them.
+///
+/// # Errors
+///
+/// This function fails if
+///  - the system cannot be suspended to RAM, with [`Error::InvalidArgs`];
+///  - other CPUs are online, with [`Error::NotEnoughResources`];
+///  - a device fails to suspend, with the error returned by the device;
+///  - the hardware fails to enter the sleeping state, with [`Error::IoError`].
+///
+/// If this function fails, the devices that have been suspended are resumed.
+pub fn suspend() -> Result<()> {
+    let Some(handler) = SUSPEND_HANDLER.get() else {
+        return Err(Error::InvalidArgs);
+    };
+    if !arch_can_suspend() {
+        return Err(Error::InvalidArgs);
+    }
+
+    let devices = DEVICE_PM_OPS.lock().clone();
+    suspend_devices(&devices)?;
+
+    let res = {
+        let _irq_guard = crate::irq::disable_local();
+        arch_suspend(*handler)
+    };
+    if let Err(err) = res {
+        log::warn!("Failed to enter the suspend-to-RAM state: {:?}", err);
+    }
+
+    resume_devices(&devices);
+
+    res
+}
+
+/// Suspends the devices in the reverse order.
+///
+/// If a device fails to suspend, the devices that have been suspended are resumed.
+fn suspend_devices(devices: &[Arc<dyn DevicePmOps>]) -> Result<()> {
+    for (index, device) in devices.iter().enumerate().rev() {
+        if let Err(err) = device.suspend() {
+            log::warn!("Failed to suspend device {}: {:?}", device.name(), err);
+            resume_devices(&devices[index + 1..]);
+            return Err(err);
+        }
+    }
+
+    Ok(())
+}
+
+fn resume_devices(devices: &[Arc<dyn DevicePmOps>]) {
+    for device in devices.iter() {
+        device.resume();
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn arch_can_suspend() -> bool {
+    false
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn arch_suspend(_handler: fn()) -> Result<()> {
+    Err(Error::InvalidArgs)
+}
+
+/// The maximum number of segments in a [`KexecImage`].
+pub const KEXEC_MAX_SEGMENTS: usize = 16;
+
+/// A new kernel image to be executed by [`kexec`].
+///
+/// The image consists of segments, each of which is copied to its destination in the physical
+/// memory right before the new kernel is executed. The frames that hold the segments never
+/// overlap with any destinations, so the segments can be copied without corrupting each other.
+pub struct KexecImage {
+    segments: Vec<KexecSegment>,
+    entry: Paddr,
+    boot_arg: usize,
+}
+
+/// A segment of a [`KexecImage`].
+pub struct KexecSegment {
+    frames: USegment,
+    dest: Paddr,
+    len: usize,
+}
+
+impl KexecImage {
+    /// Creates an image whose segments will be copied to the destinations.
+    ///
+    /// The frames of the segments are zeroed and can be filled by the caller later. When the new
+    /// kernel is executed, the CPU jumps to `entry`, and `boot_arg` is passed to the new kernel
+    /// according to the boot protocol of the architecture (e.g., in `RSI` for the Linux x86
+    /// 64-bit boot protocol).
+    ///
+    /// # Errors
+    ///
+    /// This method fails if
+    ///  - there are too many destinations, or the destinations are not page-aligned, overlap with
+    ///    each other, or are not in the physical memory, with [`Error::InvalidArgs`];
+    ///  - the frames cannot be allocated, with [`Error::NoMemory`].
+    pub fn new(dests: &[Range<Paddr>], entry: Paddr, boot_arg: usize) -> Result<Self> {
+        check_dests(dests, is_ram)?;
+
+        let mut image = Self {
+            segments: Vec::with_capacity(dests.len()),
+            entry,
+            boot_arg,
+        };
+        for dest in dests.iter() {
+            let len = dest.end - dest.start;
+            let frames = alloc_segment_avoiding(len / PAGE_SIZE, dests)?;
+            image.segments.push(KexecSegment {
+                frames: frames.into(),
+                dest: dest.start,
+                len,
+            });
+        }
+
+        Ok(image)
+    }
+
+    /// Creates an image for the crash kernel.
+    ///
+    /// The destinations must be in the memory reserved for the crash kernel (see
+    /// [`crash_kernel_region`]). Unlike [`Self::new`], the segments are placed directly at their
+    /// destinations, so nothing needs to be copied when the crash kernel is executed. Therefore,
+    /// the previous crash image that uses the same memory should be unloaded first.
+    ///
+    /// # Errors
+    ///
+    /// This method fails with [`Error::InvalidArgs`] if there are too many destinations, or the
+    /// destinations are not page-aligned, overlap with each other, or are not in the reserved
+    /// memory.
+    pub fn new_crash(dests: &[Range<Paddr>], entry: Paddr, boot_arg: usize) -> Result<Self> {
+        let region = crash_kernel_region().ok_or(Error::InvalidArgs)?;
+        check_dests(dests, |dest| {
+            region.start <= dest.start && dest.end <= region.end
+        })?;
+
+        // The frames of the reserved memory are held forever, so that they will never be returned
+        // to the frame allocator.
+        let region_frames = CRASH_KERNEL_FRAMES.try_call_once(|| {
+            Segment::from_unused(region.clone(), |_| ())
+                .map(USegment::from)
+                .map_err(|_| Error::NotEnoughResources)
+        })?;
+
+        let mut image = Self {
+            segments: Vec::with_capacity(dests.len()),
+            entry,
+            boot_arg,
+        };
+        for dest in dests.iter() {
+            let frames = region_frames.slice(&(dest.start - region.start..dest.end - region.start));
+            frames.writer().fill_zeros(dest.len());
+            image.segments.push(KexecSegment {
+                frames,
+                dest: dest.start,
+                len: dest.len(),
+            });
+        }
+
+        Ok(image)
+    }
+
+    /// Returns the segments.
+    pub fn segments(&self) -> &[KexecSegment] {
+        &self.segments
+    }
+
+    /// Returns the entry of the new kernel.
+    pub fn entry(&self) -> Paddr {
+        self.entry
+    }
+
+    /// Returns the argument passed to the new kernel.
+    pub fn boot_arg(&self) -> usize {
+        self.boot_arg
+    }
+
+    /// Returns the destinations of the segments.
+    pub(crate) fn dests(&self) -> Vec<Range<Paddr>> {
+        self.segments
+            .iter()
+            .map(|segment| segment.dest..segment.dest_end())
+            .collect()
+    }
+}
+
+impl KexecSegment {
+    /// Returns the frames that hold the content of the segment.
+    pub fn frames(&self) -> &USegment {
+        &self.frames
+    }
+
+    /// Returns the physical address where the segment will be copied to.
+    pub fn dest(&self) -> Paddr {
+        self.dest
+    }
+
+    /// Returns the end of the destination.
+    pub fn dest_end(&self) -> Paddr {
+        self.dest + self.len
+    }
+
+    /// Returns the length of the segment.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the segment is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// Checks whether the destinations are valid for a [`KexecImage`].
+fn check_dests(dests: &[Range<Paddr>], is_valid: impl Fn(&Range<Paddr>) -> bool) -> Result<()> {
+    if dests.len() > KEXEC_MAX_SEGMENTS {
+        return Err(Error::InvalidArgs);
+    }
+
+    for (index, dest) in dests.iter().enumerate() {
+        if dest.start % PAGE_SIZE != 0 || dest.end % PAGE_SIZE != 0 || dest.is_empty() {
+            return Err(Error::InvalidArgs);
+        }
+        if dests[..index]
+            .iter()
+            .any(|other| other.start < dest.end && dest.start < other.end)
+        {
+            return Err(Error::InvalidArgs);
+        }
+        if !is_valid(dest) {
+            return Err(Error::InvalidArgs);
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns whether the range is in the physical memory that can be used by the kernel.
+fn is_ram(range: &Range<Paddr>) -> bool {
+    let mut regions = crate::boot::boot_info()
+        .memory_regions
+        .iter()
+        .filter(|region| {
+            matches!(
+                region.typ(),
+                MemoryRegionType::Usable
+                    | MemoryRegionType::Reclaimable
+                    | MemoryRegionType::Kernel
+                    | MemoryRegionType::Module
+            )
+        })
+        .map(|region| region.base()..region.end())
+        .collect::<Vec<_>>();
+    regions.sort_unstable_by_key(|region| region.start);
+
+    // Check whether the range is covered by the regions, which may overlap or be adjacent.
+    let mut covered_end = range.start;
+    for region in regions {
+        if region.start > covered_end {
+            break;
+        }
+        covered_end = covered_end.max(region.end);
+        if covered_end >= range.end {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Allocates contiguous frames that do not overlap with the destinations.
+pub(crate) fn alloc_segment_avoiding(
+    nframes: usize,
+    dests: &[Range<Paddr>],
+) -> Result<Segment<()>> {
+    // The frames that overlap with the destinations are held until the allocation succeeds, so
+    // that they will not be allocated again.
+    let mut overlapping = Vec::new();
+
+    loop {
+        let frames = FrameAllocOptions::new().alloc_segment(nframes)?;
+        let range = frames.paddr()..frames.paddr() + nframes * PAGE_SIZE;
+        if !dests
+            .iter()
+            .any(|dest| dest.start < range.end && range.start < dest.end)
+        {
+            return Ok(frames);
+        }
+        overlapping.push(frames);
+    }
+}
+
+/// Returns whether a new kernel can be executed by [`kexec`].
+pub fn can_kexec() -> bool {
+    arch_can_kexec()
+}
+
+/// Executes a new kernel, i.e., kexec.
+///
+/// The devices are suspended in the same way as [`suspend`] to stop them from accessing the
+/// memory, and the other CPUs are stopped. Then the segments of the image are copied to their
+/// destinations, and the current CPU jumps to the entry of the new kernel.
+///
+/// The caller should make sure that no other tasks are running.
+///
+/// This function does not return if it succeeds.
+///
+/// # Errors
+///
+/// This function fails if
+///  - a new kernel cannot be executed, with [`Error::InvalidArgs`];
+///  - the memory for jumping to the new kernel cannot be allocated, with [`Error::NoMemory`];
+///  - a device fails to suspend, with the error returned by the device.
+///
+/// If this function fails, the devices that have been suspended are resumed.
+pub fn kexec(image: &KexecImage) -> Result<Infallible> {
+    if !arch_can_kexec() {
+        return Err(Error::InvalidArgs);
+    }
+
+    let jump = KexecJump::new(image)?;
+
+    let devices = DEVICE_PM_OPS.lock().clone();
+    suspend_devices(&devices)?;
+
+    let irq_guard = crate::irq::disable_local();
+    stop_other_cpus(&irq_guard);
+
+    jump.jump()
+}
+
+/// The crash image and the prepared jump to it, which are used when the kernel panics.
+///
+/// The jump is prepared in advance, since allocating memory is not reliable after a panic.
+static CRASH_KEXEC: SpinLock<Option<(KexecImage, KexecJump)>> = SpinLock::new(None);
+
+/// Loads the image of the crash kernel, which will be executed by [`crash_kexec`].
+///
+/// The previously loaded crash image is replaced.
+///
+/// # Errors
+///
+/// This function fails if
+///  - a new kernel cannot be executed, with [`Error::InvalidArgs`];
+///  - the memory for jumping to the crash kernel cannot be allocated, with [`Error::NoMemory`].
+pub fn load_crash_image(image: KexecImage) -> Result<()> {
+    if !arch_can_kexec() {
+        return Err(Error::InvalidArgs);
+    }
+
+    let jump = KexecJump::new(&image)?;
+    *CRASH_KEXEC.lock() = Some((image, jump));
+
+    Ok(())
+}
+
+/// Unloads the image of the crash kernel, if any.
+pub fn unload_crash_image() {
+    let crash = CRASH_KEXEC.lock().take();
+    drop(crash);
+}
+
+/// Returns whether the image of the crash kernel is loaded.
+pub fn is_crash_image_loaded() -> bool {
+    CRASH_KEXEC.lock().is_some()
+}
+
+/// Executes the crash kernel after the kernel panics.
+///
+/// Unlike [`kexec`], the devices are not suspended, since the kernel may be in an inconsistent
+/// state. The crash kernel is expected to reset the devices by itself.
+///
+/// This function does not return if the crash image is loaded.
+pub fn crash_kexec() {
+    let irq_guard = crate::irq::disable_local();
+
+    // The lock may be held by the current CPU if the panic happens while loading the image.
+    let Some(mut crash) = CRASH_KEXEC.try_lock() else {
+        return;
+    };
+    let Some((_image, jump)) = crash.take() else {
+        return;
+    };
+
+    log::error!("Executing the crash kernel...");
+
+    // TODO: Save the registers of all CPUs as ELF notes for the crash kernel.
+    stop_other_cpus(&irq_guard);
+
+    jump.jump()
+}
+
+/// Stops the other CPUs by halting them with the local IRQs disabled.
+fn stop_other_cpus(irq_guard: &DisabledLocalIrqGuard) {
+    /// The maximum number of iterations to wait for the other CPUs to stop.
+    const MAX_WAIT_ITERATIONS: usize = 100_000_000;
+
+    static NR_STOPPED: AtomicUsize = AtomicUsize::new(0);
+
+    let mut targets = CpuSet::new_full();
+    targets.remove(irq_guard.current_cpu());
+    let nr_targets = targets.count();
+    if nr_targets == 0 {
+        return;
+    }
+
+    NR_STOPPED.store(0, Ordering::Relaxed);
+    crate::smp::inter_processor_call(&targets, || {
+        NR_STOPPED.fetch_add(1, Ordering::Release);
+        disable_local_and_halt();
+    });
+
+    // Do not wait forever, since a CPU may have been halted with the local IRQs disabled.
+    for _ in 0..MAX_WAIT_ITERATIONS {
+        if NR_STOPPED.load(Ordering::Acquire) == nr_targets {
+            return;
+        }
+        core::hint::spin_loop();
+    }
+    log::warn!("Failed to stop all the other CPUs");
+}
+
+/// The memory reserved for the crash kernel.
+static CRASH_KERNEL_REGION: Once<Range<Paddr>> = Once::new();
+/// The frames of the memory reserved for the crash kernel.
+static CRASH_KERNEL_FRAMES: Once<USegment> = Once::new();
+
+/// The alignment of the memory reserved for the crash kernel, which is the same as Linux's.
+const CRASH_KERNEL_ALIGN: usize = 16 * 1024 * 1024;
+/// The end of the memory where the crash kernel can be placed.
+///
+/// The crash kernel is placed in the low memory so that it can be booted by any boot protocol.
+const CRASH_KERNEL_MAX_ADDR: Paddr = 0x1_0000_0000;
+
+/// Returns the memory reserved for the crash kernel.
+///
+/// The memory is reserved according to the `crashkernel=SIZE[@OFFSET]` argument in the kernel
+/// command line. This function returns `None` if no memory is reserved.
+pub fn crash_kernel_region() -> Option<Range<Paddr>> {
+    CRASH_KERNEL_REGION.get().cloned()
+}
+
+/// Reserves the memory for the crash kernel.
+///
+/// The reserved memory must not overlap with the `allocated` ranges, and it must be excluded from
+/// the frame allocator by the caller. If no memory is reserved, an empty range is returned.
+///
+/// This function should be called only once when initializing the frame allocator.
+pub(crate) fn reserve_crash_kernel_region(allocated: &[Range<Paddr>]) -> Range<Paddr> {
+    let kcmdline = crate::boot::EARLY_INFO.get().unwrap().kernel_cmdline;
+    let Some(arg) = kcmdline
+        .split(' ')
+        .find_map(|arg| arg.strip_prefix("crashkernel="))
+    else {
+        return 0..0;
+    };
+
+    let Some(region) = parse_crashkernel(arg)
+        .and_then(|(size, offset)| find_crash_kernel_region(size, offset, allocated))
+    else {
+        log::warn!(
+            "Failed to reserve memory for the crash kernel: crashkernel={}",
+            arg
+        );
+        return 0..0;
+    };
+
+    log::info!("Reserved memory for the crash kernel: {:#x?}", region);
+    CRASH_KERNEL_REGION.call_once(|| region.clone());
+
+    region
+}
+
+/// Parses the `SIZE[@OFFSET]` argument of `crashkernel=`.
+fn parse_crashkernel(arg: &str) -> Option<(usize, Option<Paddr>)> {
+    let parse_size = |value: &str| {
+        let (num, shift) = match value.as_bytes().last()? {
+            b'K' | b'k' => (&value[..value.len() - 1], 10),
+            b'M' | b'm' => (&value[..value.len() - 1], 20),
+            b'G' | b'g' => (&value[..value.len() - 1], 30),
+            _ => (value, 0),
+        };
+        let num = match num.strip_prefix("0x") {
+            Some(hex) => usize::from_str_radix(hex, 16).ok()?,
+            None => num.parse().ok()?,
+        };
+        num.checked_mul(1 << shift)
+    };
+
+    match arg.split_once('@') {
+        Some((size, offset)) => Some((parse_size(size)?, Some(parse_size(offset)?))),
+        None => Some((parse_size(arg)?, None)),
+    }
+}
+
+/// Finds the memory for the crash kernel in the usable memory.
+///
+/// If the offset is not specified, the highest aligned memory below [`CRASH_KERNEL_MAX_ADDR`]
+/// is used, like Linux does.
+fn find_crash_kernel_region(
+    size: usize,
+    offset: Option<Paddr>,
+    allocated: &[Range<Paddr>],
+) -> Option<Range<Paddr>> {
+    if size == 0 {
+        return None;
+    }
+    let size = size.checked_next_multiple_of(PAGE_SIZE)?;
+
+    let regions = &crate::boot::EARLY_INFO.get().unwrap().memory_regions;
+    let is_free = |range: &Range<Paddr>| {
+        regions.iter().any(|region| {
+            region.typ() == MemoryRegionType::Usable
+                && region.base() <= range.start
+                && range.end <= region.end()
+        }) && !allocated
+            .iter()
+            .any(|other| other.start < range.end && range.start < other.end)
+    };
+
+    if let Some(offset) = offset {
+        let range = offset..offset.checked_add(size)?;
+        return (offset % PAGE_SIZE == 0 && is_free(&range)).then_some(range);
+    }
+
+    let mut start =
+        CRASH_KERNEL_MAX_ADDR.checked_sub(size)? / CRASH_KERNEL_ALIGN * CRASH_KERNEL_ALIGN;
+    while start > 0 {
+        let range = start..start + size;
+        if is_free(&range) {
+            return Some(range);
+        }
+        start -= CRASH_KERNEL_ALIGN;
+    }
+
+    None
+}
+
+/// Reads the memory of the crashed kernel in the crash kernel.
+///
+/// The memory of the crashed kernel (i.e., the old memory) is outside the physical memory of the
+/// current kernel, and its layout is usually described by the ELF core header passed by the
+/// crashed kernel. This function reads from `range` to `writer`, returning the number of bytes
+/// read.
+///
+/// # Errors
+///
+/// This function fails with [`Error::AccessDenied`] if the range overlaps with the physical
+/// memory of the current kernel.
+pub fn read_old_memory(range: Range<Paddr>, writer: &mut VmWriter) -> Result<usize> {
+    let len = range.len().min(writer.avail());
+    if len == 0 {
+        return Ok(0);
+    }
+    let range = range.start..range.start + len;
+
+    if crate::boot::boot_info()
+        .memory_regions
+        .iter()
+        .filter(|region| region.typ().is_physical())
+        .any(|region| region.base() < range.end && range.start < region.end())
+    {
+        return Err(Error::AccessDenied);
+    }
+
+    // The old memory cannot be accessed in TD guests, where kexec is not supported anyway.
+    #[cfg(target_arch = "x86_64")]
+    crate::arch::if_tdx_enabled!({
+        return Err(Error::AccessDenied);
+    });
+
+    // SAFETY: The range is not in the physical memory of the current kernel, so reading from it
+    // does not affect the current kernel.
+    let io_mem: IoMem = unsafe { IoMem::new(range, PageFlags::R, CachePolicy::Writeback) };
+    writer
+        .write_fallible(&mut io_mem.reader())
+        .map_err(|(err, _)| err)
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn arch_can_kexec() -> bool {
+    false
+}
+
+/// The state to jump to a new kernel, which cannot be created without the architecture support.
+#[cfg(not(target_arch = "x86_64"))]
+enum KexecJump {}
+
+#[cfg(not(target_arch = "x86_64"))]
+impl KexecJump {
+    fn new(_image: &KexecImage) -> Result<Self> {
+        Err(Error::InvalidArgs)
+    }
+
+    fn jump(self) -> ! {
+        match self {}
+    }
+}
+
+/// The power management operations of a device.
+pub trait DevicePmOps: Send + Sync {
+    /// Returns the name of the device.
+    fn name(&self) -> &str;
+
+    /// Quiesces the device before the system sleeps.
+    ///
+    /// This method is called with the local IRQs enabled, so it can wait for the pending
+    /// requests to complete.
+    fn suspend(&self) -> Result<()>;
+
+    /// Restores the device after the system wakes up.
+    ///
+    /// Note that the device may have been reset during the sleep, so its state should be
+    /// restored from scratch.
+    fn resume(&self);
+}
+
+static DEVICE_PM_OPS: SpinLock<Vec<Arc<dyn DevicePmOps>>> = SpinLock::new(Vec::new());
+
+/// Registers the power management operations of a device.
+///
+/// A device should be registered after the devices it depends on (e.g., its bus), so that it is
+/// suspended before and resumed after them.
+pub fn register_device_pm_ops(ops: Arc<dyn DevicePmOps>) {
+    DEVICE_PM_OPS.lock().push(ops);
+}
+
 fn machine_halt() -> ! {
     log::error!("Halting the machine...");
 
@@ -11,7 +11,7 @@ use spin::Once;
 
 use crate::{
     arch::{irq::HwCpuId, trap::TrapFrame},
-    cpu::{CpuSet, PinCurrentCpu},
+    cpu::{CpuId, CpuSet, PinCurrentCpu},
     cpu_local, irq,
     sync::SpinLock,
     util::id_set::Id,
@@ -79,6 +79,15 @@ impl IpiSender {
     }
 }
 
+/// Returns the hardware ID of the CPU.
+///
+/// This function returns `None` if the hardware IDs are unknown, i.e., before all the APs are
+/// booted.
+pub(crate) fn hw_cpu_id_of(cpu_id: CpuId) -> Option<HwCpuId> {
+    let ipi_sender = IPI_SENDER.get()?;
+    Some(ipi_sender.hw_cpu_ids[cpu_id.as_usize()])
+}
+
 cpu_local! {
     static CALL_QUEUES: SpinLock<VecDeque<fn()>> = SpinLock::new(VecDeque::new());
 }
@@ -0,0 +1,937 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The lock dependency validator.
+//!
+//! The validator is enabled by the `lockdep` feature. It records the order in which the locks
+//! ([`SpinLock`], [`RwLock`], [`Mutex`], and [`RwMutex`]) are acquired, and reports the
+//! following bugs when they occur for the first time, even if no deadlock actually happens:
+//!
+//! - **Circular locking dependencies.** A lock of class `B` is acquired while a lock of class `A`
+//!   is held, but a lock of class `A` has been acquired while a lock of class `B` is held,
+//!   possibly through the locks of other classes.
+//! - **Inconsistent IRQ usage.** The locks of a class are acquired in the interrupt context, and
+//!   are also held in the task context with local IRQs enabled.
+//! - **Sleeping in atomic mode.** A sleeping lock ([`Mutex`] or [`RwMutex`]) is acquired in
+//!   [the atomic mode](crate::task::atomic_mode), even if the lock is not contended.
+//!
+//! The locks are grouped into _classes_ by the locations where they are created, so the order
+//! learned from some locks applies to all the locks created by the same code. The acquisitions
+//! of the locks in the same class are not validated against each other, and the dependencies
+//! are only recorded between the locks acquired in the same interrupt level.
+//!
+//! A reader does not wait for the other readers. So a cycle is not reported if every path
+//! through it contains a lock that is read while it is read by another task.
+//!
+//! The validator turns itself off after the first report, since the following reports are
+//! likely to be consequences of the first one.
+//!
+//! [`SpinLock`]: super::SpinLock
+//! [`RwLock`]: super::RwLock
+//! [`Mutex`]: super::Mutex
+//! [`RwMutex`]: super::RwMutex
+
+use core::{
+    cell::UnsafeCell,
+    fmt,
+    ops::{Deref, DerefMut},
+    panic::Location,
+    ptr,
+    sync::atomic::{AtomicBool, AtomicPtr, AtomicU8, AtomicU32, Ordering},
+};
+
+use crate::{
+    arch::irq as arch_irq,
+    irq::{DisabledLocalIrqGuard, InterruptLevel, disable_local},
+    task::{Task, atomic_mode::is_in_atomic_mode},
+};
+
+/// The maximum number of lock classes.
+///
+/// It must be a power of two.
+const MAX_CLASSES: usize = 4096;
+/// The maximum number of dependencies between lock classes.
+const MAX_DEPS: usize = 16384;
+/// The maximum number of locks that a task can hold at the same time.
+const MAX_HELD_LOCKS: usize = 48;
+/// The maximum number of classes that are printed in a cycle.
+const MAX_CYCLE_LEN: usize = 16;
+
+/// The number of search states, which are the classes with the modes that they are acquired in.
+const NR_STATES: usize = MAX_CLASSES * 2;
+/// The number of slots in [`DEP_SET`], which is large enough to keep the probes short.
+const DEP_SET_SIZE: usize = MAX_DEPS * 2;
+
+/// Whether the validator is enabled.
+static IS_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// The class of a lock.
+///
+/// A class is identified by the location where the locks of the class are created.
+#[derive(Clone, Copy)]
+pub(super) struct LockClassKey(&'static Location<'static>);
+
+impl LockClassKey {
+    /// Returns the class of the locks created at the caller's location.
+    #[track_caller]
+    pub(super) const fn new() -> Self {
+        Self(Location::caller())
+    }
+}
+
+impl fmt::Debug for LockClassKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LockClassKey({})", self.0)
+    }
+}
+
+/// The mode in which a lock is acquired.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum LockMode {
+    /// The lock is shared with other readers.
+    Read,
+    /// The lock is exclusive, including the upgradeable reads.
+    Write,
+}
+
+impl LockMode {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Write => "write",
+        }
+    }
+
+    /// Returns the mode of a search state or a dependency from its bit, which is set for reads.
+    fn from_bit(bit: u16) -> Self {
+        if bit != 0 { Self::Read } else { Self::Write }
+    }
+
+    fn bit(self) -> u16 {
+        match self {
+            Self::Read => 1,
+            Self::Write => 0,
+        }
+    }
+}
+
+/// How a task waits for a lock when the lock is contended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum LockKind {
+    /// The task spins, so the lock can be acquired in atomic mode.
+    Spinning,
+    /// The task sleeps, so the lock must not be acquired in atomic mode.
+    Sleeping,
+}
+
+/// Validates the acquisition of a lock before waiting for the lock.
+///
+/// The dependencies from the locks held by the current task to the lock are recorded, and the
+/// circular dependencies are reported.
+#[track_caller]
+pub(super) fn validate_acquire(key: LockClassKey, mode: LockMode, kind: LockKind) {
+    if !IS_ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+
+    if kind == LockKind::Sleeping && is_in_atomic_mode() {
+        report_sleeping_in_atomic(key, Location::caller());
+        return;
+    }
+
+    let Some(class) = class_index(key) else {
+        return;
+    };
+    let Some(held_locks) = held_locks_snapshot() else {
+        return;
+    };
+
+    let irq_level = InterruptLevel::current().as_u8();
+    for held in held_locks.iter() {
+        if held.irq_level != irq_level || held.class == class {
+            continue;
+        }
+
+        if let Err(cycle) = add_dep(held, class, mode) {
+            report_cycle(held, class, mode, Location::caller(), &cycle);
+            return;
+        }
+    }
+}
+
+/// Records that the current task has acquired a lock.
+pub(super) fn acquired(key: LockClassKey, lock: *const (), mode: LockMode) {
+    if !IS_ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let Some(class) = class_index(key) else {
+        return;
+    };
+
+    let irq_level = InterruptLevel::current();
+    mark_usage(class, irq_level, mode);
+
+    let held = HeldLock {
+        addr: lock.addr(),
+        class,
+        mode,
+        irq_level: irq_level.as_u8(),
+    };
+    if with_held_locks(|held_locks| held_locks.push(held)) == Some(false) {
+        report_overflow("held locks");
+    }
+}
+
+/// Records that the current task has released a lock.
+pub(super) fn released(lock: *const ()) {
+    if !IS_ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+
+    with_held_locks(|held_locks| held_locks.remove(lock.addr()));
+}
+
+/// Prints the locks held by the current task.
+pub(crate) fn print_held_locks() {
+    if !IS_ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+
+    if let Some(held_locks) = held_locks_snapshot() {
+        held_locks.print();
+    }
+}
+
+/// The locks held by a task.
+pub(crate) struct HeldLocks(UnsafeCell<HeldLockStack>);
+
+// SAFETY: The held locks of a task are only accessed by the task itself with local IRQs
+// disabled. See `with_held_locks`.
+unsafe impl Sync for HeldLocks {}
+
+impl HeldLocks {
+    pub(crate) const fn new() -> Self {
+        Self(UnsafeCell::new(HeldLockStack {
+            locks: [HeldLock::EMPTY; MAX_HELD_LOCKS],
+            depth: 0,
+        }))
+    }
+}
+
+impl fmt::Debug for HeldLocks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HeldLocks").finish_non_exhaustive()
+    }
+}
+
+#[derive(Clone, Copy)]
+struct HeldLockStack {
+    locks: [HeldLock; MAX_HELD_LOCKS],
+    depth: usize,
+}
+
+impl HeldLockStack {
+    fn iter(&self) -> impl Iterator<Item = &HeldLock> {
+        self.locks[..self.depth].iter()
+    }
+
+    /// Pushes a lock, returning `false` if there are too many held locks.
+    fn push(&mut self, held: HeldLock) -> bool {
+        if self.depth == MAX_HELD_LOCKS {
+            return false;
+        }
+
+        self.locks[self.depth] = held;
+        self.depth += 1;
+        true
+    }
+
+    /// Removes the most recently acquired entry of the lock.
+    ///
+    /// The lock may not be found if it was acquired before the validator recorded it.
+    fn remove(&mut self, addr: usize) {
+        let Some(index) = self.locks[..self.depth]
+            .iter()
+            .rposition(|held| held.addr == addr)
+        else {
+            return;
+        };
+
+        self.locks.copy_within(index + 1..self.depth, index);
+        self.depth -= 1;
+    }
+
+    fn print(&self) {
+        log::error!("{} lock(s) held by the current task:", self.depth);
+        for (i, held) in self.iter().enumerate() {
+            log::error!(
+                "  #{}: {:#x} of class {} ({}, interrupt level {})",
+                i,
+                held.addr,
+                class_location(held.class),
+                held.mode.as_str(),
+                held.irq_level,
+            );
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct HeldLock {
+    /// The address of the lock.
+    addr: usize,
+    class: u16,
+    mode: LockMode,
+    /// The interrupt level in which the lock is acquired.
+    irq_level: u8,
+}
+
+impl HeldLock {
+    const EMPTY: Self = Self {
+        addr: 0,
+        class: 0,
+        mode: LockMode::Write,
+        irq_level: 0,
+    };
+}
+
+/// Accesses the locks held by the current task.
+///
+/// Returns `None` if there is no current task.
+fn with_held_locks<R>(f: impl FnOnce(&mut HeldLockStack) -> R) -> Option<R> {
+    // Locks may be acquired in the interrupt handlers, which record them in the interrupted task.
+    let _irq_guard = disable_local();
+    let current = Task::current()?;
+    // SAFETY: The held locks of the current task are only accessed on the current CPU by the
+    // current task, and local IRQs are disabled. So there are no other references to them.
+    let held_locks = unsafe { &mut *current.held_locks().0.get() };
+    Some(f(held_locks))
+}
+
+fn held_locks_snapshot() -> Option<HeldLockStack> {
+    with_held_locks(|held_locks| *held_locks)
+}
+
+/// The keys of the known classes, which are indexed by their hashes.
+static CLASS_KEYS: [AtomicPtr<Location<'static>>; MAX_CLASSES] =
+    [const { AtomicPtr::new(ptr::null_mut()) }; MAX_CLASSES];
+
+/// Returns the index of the class, which is registered if it is not known yet.
+///
+/// Returns `None` if there are too many classes.
+fn class_index(key: LockClassKey) -> Option<u16> {
+    const HASH_SHIFT: u32 = u64::BITS - MAX_CLASSES.trailing_zeros();
+
+    let key_ptr = ptr::from_ref(key.0).cast_mut();
+    let mut index =
+        ((key_ptr.addr() as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) >> HASH_SHIFT) as usize;
+
+    for _ in 0..MAX_CLASSES {
+        let slot = &CLASS_KEYS[index];
+        let mut current = slot.load(Ordering::Acquire);
+        if current.is_null() {
+            current = match slot.compare_exchange(
+                ptr::null_mut(),
+                key_ptr,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return Some(index as u16),
+                Err(current) => current,
+            };
+        }
+        if current == key_ptr {
+            return Some(index as u16);
+        }
+
+        index = (index + 1) % MAX_CLASSES;
+    }
+
+    report_overflow("lock classes");
+    None
+}
+
+fn class_location(class: u16) -> &'static Location<'static> {
+    let key_ptr = CLASS_KEYS[class as usize].load(Ordering::Acquire);
+    // SAFETY: The index of a class is only handed out after the key is stored, and a key is a
+    // reference to a `'static` location that is never removed.
+    unsafe { &*key_ptr }
+}
+
+/// The ways in which the locks of the classes are used. See [`mark_usage`].
+static CLASS_USAGE: [AtomicU8; MAX_CLASSES] = [const { AtomicU8::new(0) }; MAX_CLASSES];
+
+const USED_IN_IRQ_READ: u8 = 1 << 0;
+const USED_IN_IRQ_WRITE: u8 = 1 << 1;
+const ENABLED_IRQ_READ: u8 = 1 << 2;
+const ENABLED_IRQ_WRITE: u8 = 1 << 3;
+
+/// Records how the lock of the class is acquired and reports the inconsistent IRQ usage.
+///
+/// If the lock is held in the task context with local IRQs enabled, an interrupt handler that
+/// acquires a lock of the same class on the same CPU may spin forever. Readers do not wait for
+/// each other, so it is fine if the lock is only read in both contexts.
+fn mark_usage(class: u16, irq_level: InterruptLevel, mode: LockMode) {
+    let usage = match (irq_level.is_interrupt_context(), mode) {
+        (true, LockMode::Read) => USED_IN_IRQ_READ,
+        (true, LockMode::Write) => USED_IN_IRQ_WRITE,
+        // The lock guard may have disabled local IRQs.
+        (false, _) if !arch_irq::is_local_enabled() => return,
+        (false, LockMode::Read) => ENABLED_IRQ_READ,
+        (false, LockMode::Write) => ENABLED_IRQ_WRITE,
+    };
+
+    let old_usage = CLASS_USAGE[class as usize].fetch_or(usage, Ordering::Relaxed);
+    if old_usage & usage != 0 {
+        return;
+    }
+
+    let new_usage = old_usage | usage;
+    let is_inconsistent = (new_usage & USED_IN_IRQ_WRITE != 0
+        && new_usage & (ENABLED_IRQ_READ | ENABLED_IRQ_WRITE) != 0)
+        || (new_usage & USED_IN_IRQ_READ != 0 && new_usage & ENABLED_IRQ_WRITE != 0);
+    if is_inconsistent {
+        report_inconsistent_usage(class, irq_level, mode);
+    }
+}
+
+/// The known dependencies, which are used to check the dependencies without locking the graph.
+///
+/// An empty slot is zero. The dependencies are inserted with the graph locked, and are never
+/// removed.
+static DEP_SET: [AtomicU32; DEP_SET_SIZE] = [const { AtomicU32::new(0) }; DEP_SET_SIZE];
+
+fn dep_set_key(from: u16, to: u16, mode: u8) -> u32 {
+    (((from as u32) << 14) | ((to as u32) << 2) | mode as u32) + 1
+}
+
+fn dep_set_contains(key: u32) -> bool {
+    let mut index = key as usize * 31 % DEP_SET_SIZE;
+    loop {
+        match DEP_SET[index].load(Ordering::Acquire) {
+            0 => return false,
+            slot_key if slot_key == key => return true,
+            _ => index = (index + 1) % DEP_SET_SIZE,
+        }
+    }
+}
+
+/// Inserts the dependency into the set.
+///
+/// The graph must be locked. There are at most [`MAX_DEPS`] dependencies, so there are always
+/// empty slots.
+fn dep_set_insert(key: u32) {
+    let mut index = key as usize * 31 % DEP_SET_SIZE;
+    while DEP_SET[index].load(Ordering::Relaxed) != 0 {
+        index = (index + 1) % DEP_SET_SIZE;
+    }
+    DEP_SET[index].store(key, Ordering::Release);
+}
+
+/// Records the dependency from a held lock to the lock to acquire.
+///
+/// Returns the existing dependencies that form a cycle with the dependency, if any.
+fn add_dep(from: &HeldLock, to: u16, to_mode: LockMode) -> Result<(), Cycle> {
+    let mode = Dep::mode(from.mode, to_mode);
+    let key = dep_set_key(from.class, to, mode);
+    if dep_set_contains(key) {
+        return Ok(());
+    }
+
+    let mut graph = GRAPH.lock();
+    if dep_set_contains(key) {
+        return Ok(());
+    }
+
+    if let Some(cycle) = graph.find_cycle(from.class, from.mode, to, to_mode) {
+        return Err(cycle);
+    }
+
+    if graph.nr_deps == MAX_DEPS {
+        drop(graph);
+        report_overflow("lock dependencies");
+        return Ok(());
+    }
+
+    graph.insert(from.class, to, mode);
+    dep_set_insert(key);
+    Ok(())
+}
+
+/// Returns whether acquiring a lock may wait for another task that holds the lock.
+fn may_block(acquired_mode: LockMode, held_mode: LockMode) -> bool {
+    !(acquired_mode == LockMode::Read && held_mode == LockMode::Read)
+}
+
+static GRAPH: GraphLock = GraphLock::new();
+
+/// A raw spin lock that protects the dependency graph.
+///
+/// It cannot be a [`SpinLock`], whose acquisition is validated by this module.
+///
+/// [`SpinLock`]: super::SpinLock
+struct GraphLock {
+    lock: AtomicBool,
+    graph: UnsafeCell<Graph>,
+}
+
+// SAFETY: The graph is only accessed with the lock held.
+unsafe impl Sync for GraphLock {}
+
+impl GraphLock {
+    const fn new() -> Self {
+        Self {
+            lock: AtomicBool::new(false),
+            graph: UnsafeCell::new(Graph {
+                heads: [0; MAX_CLASSES],
+                deps: [Dep::EMPTY; MAX_DEPS],
+                nr_deps: 0,
+                visited: [0; NR_STATES / u64::BITS as usize],
+                parents: [0; NR_STATES],
+                queue: [0; NR_STATES],
+            }),
+        }
+    }
+
+    fn lock(&self) -> GraphGuard<'_> {
+        // The graph may be locked in the interrupt handlers.
+        let irq_guard = disable_local();
+        while self
+            .lock
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            core::hint::spin_loop();
+        }
+
+        GraphGuard {
+            lock: self,
+            _irq_guard: irq_guard,
+        }
+    }
+}
+
+struct GraphGuard<'a> {
+    lock: &'a GraphLock,
+    _irq_guard: DisabledLocalIrqGuard,
+}
+
+impl Deref for GraphGuard<'_> {
+    type Target = Graph;
+
+    fn deref(&self) -> &Graph {
+        // SAFETY: The graph is locked.
+        unsafe { &*self.lock.graph.get() }
+    }
+}
+
+impl DerefMut for GraphGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Graph {
+        // SAFETY: The graph is locked.
+        unsafe { &mut *self.lock.graph.get() }
+    }
+}
+
+impl Drop for GraphGuard<'_> {
+    fn drop(&mut self) {
+        self.lock.lock.store(false, Ordering::Release);
+    }
+}
+
+/// The dependency graph of the lock classes.
+///
+/// A search state is a class together with the mode in which it is acquired, whose index is
+/// `class * 2 + mode.bit()`.
+struct Graph {
+    /// The index plus one of the first dependency of each class, or zero if there is none.
+    heads: [u16; MAX_CLASSES],
+    deps: [Dep; MAX_DEPS],
+    nr_deps: usize,
+    /// The bitmap of the visited states in the current search.
+    visited: [u64; NR_STATES / u64::BITS as usize],
+    /// The state from which each visited state is reached in the current search.
+    parents: [u16; NR_STATES],
+    /// The queue of the current search.
+    queue: [u16; NR_STATES],
+}
+
+/// A dependency, meaning that a lock of the class `to` has been acquired while a lock of
+/// another class is held.
+#[derive(Clone, Copy)]
+struct Dep {
+    to: u16,
+    /// See [`Dep::mode`].
+    mode: u8,
+    /// The index plus one of the next dependency of the same class, or zero if there is none.
+    next: u16,
+}
+
+impl Dep {
+    const EMPTY: Self = Self {
+        to: 0,
+        mode: 0,
+        next: 0,
+    };
+
+    const FROM_READ: u8 = 1 << 1;
+    const TO_READ: u8 = 1 << 0;
+
+    fn mode(from_mode: LockMode, to_mode: LockMode) -> u8 {
+        ((from_mode.bit() << 1) | to_mode.bit()) as u8
+    }
+}
+
+impl Graph {
+    fn insert(&mut self, from: u16, to: u16, mode: u8) {
+        let index = self.nr_deps;
+        self.deps[index] = Dep {
+            to,
+            mode,
+            next: self.heads[from as usize],
+        };
+        self.heads[from as usize] = (index + 1) as u16;
+        self.nr_deps += 1;
+    }
+
+    /// Searches for a path of the existing dependencies from `to` back to `from`, which forms a
+    /// deadlock together with the new dependency from `from` to `to`.
+    ///
+    /// Every lock on the path must be acquired in a mode that may wait for the task that holds
+    /// it in the next dependency.
+    fn find_cycle(
+        &mut self,
+        from: u16,
+        from_mode: LockMode,
+        to: u16,
+        to_mode: LockMode,
+    ) -> Option<Cycle> {
+        self.visited.fill(0);
+
+        let start = to * 2 + to_mode.bit();
+        self.mark_visited(start);
+        self.parents[start as usize] = start;
+        self.queue[0] = start;
+        let (mut head, mut tail) = (0, 1);
+
+        while head < tail {
+            let state = self.queue[head];
+            head += 1;
+
+            let (class, acquired_mode) = (state / 2, LockMode::from_bit(state % 2));
+            if class == from && may_block(acquired_mode, from_mode) {
+                return Some(self.cycle_to(state));
+            }
+
+            let mut next = self.heads[class as usize];
+            while next != 0 {
+                let dep = self.deps[(next - 1) as usize];
+                next = dep.next;
+
+                let dep_from_mode = LockMode::from_bit((dep.mode & Dep::FROM_READ) as u16);
+                if !may_block(acquired_mode, dep_from_mode) {
+                    continue;
+                }
+
+                let next_state = dep.to * 2 + (dep.mode & Dep::TO_READ) as u16;
+                if self.mark_visited(next_state) {
+                    self.parents[next_state as usize] = state;
+                    self.queue[tail] = next_state;
+                    tail += 1;
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Marks the state as visited, returning `false` if it has been visited.
+    fn mark_visited(&mut self, state: u16) -> bool {
+        let (word, bit) = (state as usize / 64, state as usize % 64);
+        let is_visited = self.visited[word] & (1 << bit) != 0;
+        self.visited[word] |= 1 << bit;
+        !is_visited
+    }
+
+    /// Collects the classes on the path that the search has found to the state.
+    fn cycle_to(&self, mut state: u16) -> Cycle {
+        let mut cycle = Cycle {
+            classes: [(0, LockMode::Write); MAX_CYCLE_LEN],
+            len: 0,
+            is_truncated: false,
+        };
+
+        // The path is collected backwards and then reversed.
+        loop {
+            if cycle.len == MAX_CYCLE_LEN {
+                cycle.is_truncated = true;
+                break;
+            }
+            cycle.classes[cycle.len] = (state / 2, LockMode::from_bit(state % 2));
+            cycle.len += 1;
+
+            let parent = self.parents[state as usize];
+            if parent == state {
+                break;
+            }
+            state = parent;
+        }
+        cycle.classes[..cycle.len].reverse();
+
+        cycle
+    }
+}
+
+/// The classes on a path of dependencies, with the modes in which they are acquired.
+struct Cycle {
+    classes: [(u16, LockMode); MAX_CYCLE_LEN],
+    len: usize,
+    /// Whether the classes at the beginning of the path are not collected.
+    is_truncated: bool,
+}
+
+/// Turns off the validator, returning `false` if it has been turned off.
+///
+/// The validator must be turned off before reporting, since the reporting may acquire locks.
+fn start_report() -> bool {
+    IS_ENABLED.swap(false, Ordering::Relaxed)
+}
+
+fn end_report() {
+    crate::panic::print_stack_trace();
+    log::error!("lockdep: turning off the lock dependency validator");
+}
+
+fn report_cycle(held: &HeldLock, class: u16, mode: LockMode, location: &Location, cycle: &Cycle) {
+    let held_locks = held_locks_snapshot();
+    if !start_report() {
+        return;
+    }
+
+    log::error!("lockdep: possible circular locking dependency detected");
+    log::error!(
+        "the current task is trying to acquire a lock of class {} ({}) at {},",
+        class_location(class),
+        mode.as_str(),
+        location,
+    );
+    log::error!(
+        "while holding a lock of class {} ({}),",
+        class_location(held.class),
+        held.mode.as_str(),
+    );
+    log::error!("but the following locks have been acquired in this order:");
+    if cycle.is_truncated {
+        log::error!("  ...");
+    }
+    for (class, mode) in cycle.classes[..cycle.len].iter() {
+        log::error!("  {} ({})", class_location(*class), mode.as_str());
+    }
+    if let Some(held_locks) = held_locks {
+        held_locks.print();
+    }
+    end_report();
+}
+
+fn report_inconsistent_usage(class: u16, irq_level: InterruptLevel, mode: LockMode) {
+    let held_locks = held_locks_snapshot();
+    if !start_report() {
+        return;
+    }
+
+    log::error!("lockdep: inconsistent IRQ usage of locks detected");
+    if irq_level.is_interrupt_context() {
+        log::error!(
+            "a lock of class {} is acquired ({}) in the interrupt context,",
+            class_location(class),
+            mode.as_str(),
+        );
+        log::error!("but the locks of the class have been held with local IRQs enabled");
+    } else {
+        log::error!(
+            "a lock of class {} is acquired ({}) with local IRQs enabled,",
+            class_location(class),
+            mode.as_str(),
+        );
+        log::error!("but the locks of the class have been acquired in the interrupt context");
+    }
+    if let Some(held_locks) = held_locks {
+        held_locks.print();
+    }
+    end_report();
+}
+
+fn report_sleeping_in_atomic(key: LockClassKey, location: &Location) {
+    let held_locks = held_locks_snapshot();
+    if !start_report() {
+        return;
+    }
+
+    log::error!("lockdep: sleeping lock acquired in atomic mode");
+    log::error!(
+        "a sleeping lock of class {} is acquired at {} (interrupt level {}, local IRQs {})",
+        key.0,
+        location,
+        InterruptLevel::current().as_u8(),
+        if arch_irq::is_local_enabled() {
+            "enabled"
+        } else {
+            "disabled"
+        },
+    );
+    if let Some(held_locks) = held_locks {
+        held_locks.print();
+    }
+    end_report();
+}
+
+fn report_overflow(what: &str) {
+    if !start_report() {
+        return;
+    }
+
+    log::error!("lockdep: too many {}", what);
+    log::error!("lockdep: turning off the lock dependency validator");
+}
+
+#[cfg(ktest)]
+mod test {
+    use super::*;
+    use crate::prelude::*;
+
+    fn held_lock(addr: usize, class: u16) -> HeldLock {
+        HeldLock {
+            addr,
+            class,
+            mode: LockMode::Read,
+            irq_level: 0,
+        }
+    }
+
+    #[ktest]
+    fn held_locks_released_out_of_order() {
+        let mut stack = HeldLockStack {
+            locks: [HeldLock::EMPTY; MAX_HELD_LOCKS],
+            depth: 0,
+        };
+        assert!(stack.push(held_lock(0x1000, 1)));
+        assert!(stack.push(held_lock(0x2000, 2)));
+        // A read lock can be held more than once.
+        assert!(stack.push(held_lock(0x1000, 1)));
+        assert!(stack.push(held_lock(0x3000, 3)));
+
+        stack.remove(0x2000);
+        stack.remove(0x1000);
+        // An unknown lock is ignored.
+        stack.remove(0x4000);
+
+        let addrs: Vec<usize> = stack.iter().map(|held| held.addr).collect();
+        assert_eq!(addrs, [0x1000, 0x3000]);
+    }
+
+    #[ktest]
+    fn readers_do_not_block_readers() {
+        assert!(may_block(LockMode::Write, LockMode::Write));
+        assert!(may_block(LockMode::Read, LockMode::Write));
+        assert!(may_block(LockMode::Write, LockMode::Read));
+        assert!(!may_block(LockMode::Read, LockMode::Read));
+    }
+
+    const CLASS_A: u16 = 1;
+    const CLASS_B: u16 = 2;
+    const CLASS_C: u16 = 3;
+    const CLASS_D: u16 = 4;
+
+    fn cycle_classes(cycle: &Cycle) -> Vec<(u16, LockMode)> {
+        cycle.classes[..cycle.len].to_vec()
+    }
+
+    #[ktest]
+    fn simple_cycle() {
+        static GRAPH: GraphLock = GraphLock::new();
+        let mut graph = GRAPH.lock();
+
+        // A lock of class B is acquired while a lock of class A is held.
+        let mode = Dep::mode(LockMode::Write, LockMode::Write);
+        graph.insert(CLASS_A, CLASS_B, mode);
+
+        // Acquiring a lock of class A while holding a lock of class B closes the cycle.
+        let cycle = graph
+            .find_cycle(CLASS_B, LockMode::Write, CLASS_A, LockMode::Write)
+            .unwrap();
+        assert_eq!(
+            cycle_classes(&cycle),
+            [(CLASS_A, LockMode::Write), (CLASS_B, LockMode::Write)]
+        );
+        assert!(!cycle.is_truncated);
+
+        // The reversed order of the same dependency is fine.
+        assert!(
+            graph
+                .find_cycle(CLASS_A, LockMode::Write, CLASS_B, LockMode::Write)
+                .is_none()
+        );
+    }
+
+    #[ktest]
+    fn cycle_through_readers() {
+        static GRAPH: GraphLock = GraphLock::new();
+        let mut graph = GRAPH.lock();
+
+        // A lock of class B is written while a lock of class A is read.
+        graph.insert(CLASS_A, CLASS_B, Dep::mode(LockMode::Read, LockMode::Write));
+
+        // If the lock of class A is also read, the readers do not wait for each other.
+        assert!(
+            graph
+                .find_cycle(CLASS_B, LockMode::Write, CLASS_A, LockMode::Read)
+                .is_none()
+        );
+        // If the lock of class A is written, the writer waits for the reader.
+        let cycle = graph
+            .find_cycle(CLASS_B, LockMode::Write, CLASS_A, LockMode::Write)
+            .unwrap();
+        assert_eq!(
+            cycle_classes(&cycle),
+            [(CLASS_A, LockMode::Write), (CLASS_B, LockMode::Write)]
+        );
+        // The same applies if the lock of class B is read in the new dependency.
+        assert!(
+            graph
+                .find_cycle(CLASS_B, LockMode::Read, CLASS_A, LockMode::Read)
+                .is_none()
+        );
+    }
+
+    #[ktest]
+    fn long_cycle() {
+        static GRAPH: GraphLock = GraphLock::new();
+        let mut graph = GRAPH.lock();
+
+        let mode = Dep::mode(LockMode::Write, LockMode::Write);
+        graph.insert(CLASS_A, CLASS_B, mode);
+        graph.insert(CLASS_B, CLASS_C, mode);
+        graph.insert(CLASS_C, CLASS_D, mode);
+        // A dependency that does not lead back to class D.
+        graph.insert(CLASS_B, CLASS_A, Dep::mode(LockMode::Read, LockMode::Read));
+
+        let cycle = graph
+            .find_cycle(CLASS_D, LockMode::Write, CLASS_A, LockMode::Write)
+            .unwrap();
+        assert_eq!(
+            cycle_classes(&cycle),
+            [
+                (CLASS_A, LockMode::Write),
+                (CLASS_B, LockMode::Write),
+                (CLASS_C, LockMode::Write),
+                (CLASS_D, LockMode::Write),
+            ]
+        );
+
+        // There is no path from class D to any other class.
+        assert!(
+            graph
+                .find_cycle(CLASS_A, LockMode::Write, CLASS_D, LockMode::Write)
+                .is_none()
+        );
+    }
+}
@@ -3,6 +3,8 @@
 //! Useful synchronization primitives.
 
 mod guard;
+#[cfg(feature = "lockdep")]
+pub(crate) mod lockdep;
 mod mutex;
 mod rcu;
 mod rwarc;
@@ -11,11 +13,17 @@ mod rwmutex;
 mod spin;
 mod wait;
 
-pub(crate) use self::rcu::finish_grace_period;
+pub(crate) use self::rcu::{
+    enter_idle as rcu_enter_idle, exit_idle as rcu_exit_idle, finish_grace_period,
+    on_timer_tick as rcu_on_timer_tick,
+};
 pub use self::{
     guard::{GuardTransfer, LocalIrqDisabled, PreemptDisabled, SpinGuardian, WriteIrqDisabled},
     mutex::{Mutex, MutexGuard},
-    rcu::{Rcu, RcuDrop, RcuOption, RcuOptionReadGuard, RcuReadGuard, non_null},
+    rcu::{
+        Rcu, RcuDrop, RcuOption, RcuOptionReadGuard, RcuReadGuard, call_rcu, non_null,
+        rcu_read_lock, synchronize_rcu,
+    },
     rwarc::{RoArc, RwArc},
     rwlock::{RwLock, RwLockReadGuard, RwLockUpgradeableGuard, RwLockWriteGuard},
     rwmutex::{RwMutex, RwMutexReadGuard, RwMutexUpgradeableGuard, RwMutexWriteGuard},
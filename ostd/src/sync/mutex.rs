@@ -8,20 +8,27 @@ use core::{
 };
 
 use super::WaitQueue;
+#[cfg(feature = "lockdep")]
+use super::lockdep::{self, LockClassKey, LockKind, LockMode};
 
 /// A mutex with waitqueue.
 pub struct Mutex<T: ?Sized> {
     lock: AtomicBool,
     queue: WaitQueue,
+    #[cfg(feature = "lockdep")]
+    class: LockClassKey,
     val: UnsafeCell<T>,
 }
 
 impl<T> Mutex<T> {
     /// Creates a new mutex.
+    #[cfg_attr(feature = "lockdep", track_caller)]
     pub const fn new(val: T) -> Self {
         Self {
             lock: AtomicBool::new(false),
             queue: WaitQueue::new(),
+            #[cfg(feature = "lockdep")]
+            class: LockClassKey::new(),
             val: UnsafeCell::new(val),
         }
     }
@@ -33,6 +40,9 @@ impl<T: ?Sized> Mutex<T> {
     /// This method runs in a block way until the mutex can be acquired.
     #[track_caller]
     pub fn lock(&self) -> MutexGuard<'_, T> {
+        #[cfg(feature = "lockdep")]
+        lockdep::validate_acquire(self.class, LockMode::Write, LockKind::Sleeping);
+
         self.queue.wait_until(|| self.try_lock())
     }
 
@@ -92,6 +102,13 @@ impl<'a, T: ?Sized> MutexGuard<'a, T> {
     /// The caller must ensure that the given reference of [`Mutex`] lock has been successfully acquired
     /// in the current context. When the created [`MutexGuard`] is dropped, it will unlock the [`Mutex`].
     unsafe fn new(mutex: &'a Mutex<T>) -> MutexGuard<'a, T> {
+        #[cfg(feature = "lockdep")]
+        lockdep::acquired(
+            mutex.class,
+            core::ptr::from_ref(mutex).cast(),
+            LockMode::Write,
+        );
+
         MutexGuard { mutex }
     }
 }
@@ -112,6 +129,9 @@ impl<T: ?Sized> DerefMut for MutexGuard<'_, T> {
 
 impl<T: ?Sized> Drop for MutexGuard<'_, T> {
     fn drop(&mut self) {
+        #[cfg(feature = "lockdep")]
+        lockdep::released(core::ptr::from_ref(self.mutex).cast());
+
         self.mutex.unlock();
     }
 }
@@ -18,11 +18,13 @@ use spin::once::Once;
 
 use self::monitor::RcuMonitor;
 use crate::{
+    cpu::PinCurrentCpu,
+    irq::InterruptLevel,
     panic::PanicGuard,
     task::{
         DisabledPreemptGuard,
         atomic_mode::{AsAtomicModeGuard, InAtomicMode},
-        disable_preempt,
+        disable_preempt, is_preempt_disabled,
     },
 };
 
@@ -527,8 +529,144 @@ pub unsafe fn finish_grace_period() {
     }
 }
 
+/// Enters a RCU read-side critical section.
+///
+/// The critical section lasts until the returned guard is dropped. The guard
+/// can be passed to [`Rcu::read_with`] and [`RcuOption::read_with`] to read
+/// multiple RCU-protected values in the same critical section.
+///
+/// The data that are retired via [`call_rcu`] or [`synchronize_rcu`] after
+/// the critical section begins remain valid until the critical section ends.
+pub fn rcu_read_lock() -> DisabledPreemptGuard {
+    disable_preempt()
+}
+
+/// Registers a callback that will be invoked after the current RCU grace
+/// period.
+///
+/// The callback is invoked after all the RCU read-side critical sections that
+/// exist when this function is called have ended. It is invoked in the task
+/// context, but may be on any CPU and with preemption disabled, so it must not
+/// sleep.
+pub fn call_rcu<F>(f: F)
+where
+    F: FnOnce() + Send + 'static,
+{
+    let rcu_monitor = RCU_MONITOR.get().unwrap();
+    rcu_monitor.after_grace_period(f);
+}
+
+/// Waits until all the RCU read-side critical sections that exist when this
+/// function is called have ended.
+///
+/// # Panics
+///
+/// This function will panic if it is called in the atomic mode
+/// ([`crate::task::atomic_mode`]).
+#[track_caller]
+pub fn synchronize_rcu() {
+    crate::task::atomic_mode::might_sleep();
+
+    let rcu_monitor = RCU_MONITOR.get().unwrap();
+    rcu_monitor.wait_for_grace_period();
+}
+
+/// Passes the quiescent state on the current CPU if the timer tick interrupts
+/// code outside RCU read-side critical sections.
+///
+/// This makes grace periods complete even if a CPU keeps running the same task
+/// without switching.
+pub(crate) fn on_timer_tick() {
+    // Nested interrupts may interrupt the interrupt handlers that read
+    // RCU-protected data.
+    if !matches!(InterruptLevel::current(), InterruptLevel::L1(_)) {
+        return;
+    }
+    // The top half of interrupt handling does not disable preemption, so the
+    // preemption state is the one of the interrupted code.
+    if is_preempt_disabled() {
+        return;
+    }
+
+    let Some(rcu_monitor) = RCU_MONITOR.get() else {
+        return;
+    };
+    // SAFETY: RCU read-side critical sections disable preemption or local
+    // IRQs. The interrupted code has neither of them disabled, so it is not in
+    // such a critical section.
+    unsafe {
+        rcu_monitor.finish_grace_period_in_irq();
+    }
+}
+
+/// Notifies the RCU monitor that the current CPU is about to halt.
+///
+/// The idle CPUs are woken up when a new grace period starts. After they are
+/// woken up, they should call this function again before halting, which passes
+/// the quiescent state.
+pub(crate) fn enter_idle() {
+    let Some(rcu_monitor) = RCU_MONITOR.get() else {
+        return;
+    };
+
+    let cpu = disable_preempt().current_cpu();
+    rcu_monitor.set_idle(cpu, true);
+
+    crate::task::atomic_mode::might_sleep();
+    // SAFETY: The current task can sleep, so it is not in a RCU read-side
+    // critical section.
+    unsafe {
+        rcu_monitor.finish_grace_period();
+    }
+}
+
+/// Notifies the RCU monitor that the current CPU is no longer idle.
+pub(crate) fn exit_idle() {
+    let Some(rcu_monitor) = RCU_MONITOR.get() else {
+        return;
+    };
+
+    let cpu = disable_preempt().current_cpu();
+    rcu_monitor.set_idle(cpu, false);
+}
+
 static RCU_MONITOR: Once<RcuMonitor> = Once::new();
 
 pub fn init() {
     RCU_MONITOR.call_once(RcuMonitor::new);
 }
+
+#[cfg(ktest)]
+mod test {
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+    use crate::prelude::*;
+
+    #[ktest]
+    fn synchronize_rcu_after_update() {
+        let rcu = Rcu::new(Box::new(42));
+
+        let guard = rcu_read_lock();
+        assert_eq!(**rcu.read_with(&guard), 42);
+        drop(guard);
+
+        rcu.update(Box::new(43));
+        synchronize_rcu();
+        assert_eq!(**rcu.read().get(), 43);
+    }
+
+    #[ktest]
+    fn call_rcu_invokes_callback() {
+        let is_called = Arc::new(AtomicBool::new(false));
+        let is_called_cloned = is_called.clone();
+
+        call_rcu(move || is_called_cloned.store(true, Ordering::Relaxed));
+
+        // The callbacks are invoked when a CPU switches tasks after the grace
+        // period, which happens while waiting for the grace periods.
+        while !is_called.load(Ordering::Relaxed) {
+            synchronize_rcu();
+        }
+    }
+}
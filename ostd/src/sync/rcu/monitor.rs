@@ -2,22 +2,39 @@
 
 use alloc::collections::VecDeque;
 use core::sync::atomic::{
-    AtomicBool,
-    Ordering::{self, Relaxed},
+    AtomicBool, AtomicU64,
+    Ordering::{self, Acquire, Relaxed, Release, SeqCst},
+    fence,
 };
 
 use crate::{
     cpu::{AtomicCpuSet, CpuId, CpuSet, PinCurrentCpu},
     prelude::*,
-    sync::SpinLock,
+    smp::IPI_SENDER,
+    sync::{SpinLock, WaitQueue},
     task::atomic_mode::AsAtomicModeGuard,
 };
 
 /// A RCU monitor ensures the completion of _grace periods_ by keeping track
 /// of each CPU's passing _quiescent states_.
+///
+/// A CPU passes a quiescent state when it switches tasks, when a timer tick
+/// interrupts code that is not in a read-side critical section, or when it
+/// enters or leaves the idle loop. An idle CPU is halted and does not pass
+/// quiescent states by itself, so it is woken up with an IPI when a new grace
+/// period starts.
 pub(super) struct RcuMonitor {
     is_monitoring: AtomicBool,
+    /// Whether the callbacks of completed grace periods are waiting to be
+    /// invoked.
+    has_ready_callbacks: AtomicBool,
+    /// The number of completed grace periods.
+    nr_completed: AtomicU64,
+    /// The CPUs that are halted or about to be halted in the idle loop.
+    idle_cpus: AtomicCpuSet,
     state: SpinLock<State>,
+    /// The tasks that wait for grace periods to complete.
+    wait_queue: WaitQueue,
 }
 
 impl RcuMonitor {
@@ -28,44 +45,37 @@ impl RcuMonitor {
     pub(super) fn new() -> Self {
         Self {
             is_monitoring: AtomicBool::new(false),
+            has_ready_callbacks: AtomicBool::new(false),
+            nr_completed: AtomicU64::new(0),
+            idle_cpus: AtomicCpuSet::new(CpuSet::new_empty()),
             state: SpinLock::new(State::new()),
+            wait_queue: WaitQueue::new(),
         }
     }
 
+    /// Passes the quiescent state on the current CPU and invokes the callbacks
+    /// of the completed grace periods.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that this CPU is not executing in a RCU read-side
+    /// critical section, and is in the task context where the callbacks can be
+    /// invoked.
     pub(super) unsafe fn finish_grace_period(&self) {
         // Fast path
-        if !self.is_monitoring.load(Relaxed) {
+        if !self.is_monitoring.load(Relaxed) && !self.has_ready_callbacks.load(Relaxed) {
             return;
         }
 
-        // Check if the current GP is complete after passing the quiescent state
-        // on the current CPU. If GP is complete, take the callbacks of the current
-        // GP.
-        let callbacks = {
+        let (events, callbacks) = {
             let mut state = self.state.disable_irq().lock();
             let cpu = state.as_atomic_mode_guard().current_cpu();
-            if state.current_gp.is_complete() {
-                return;
-            }
+            let events = self.pass_quiescent_state(&mut state, cpu);
 
-            state.current_gp.finish_grace_period(cpu);
-            if !state.current_gp.is_complete() {
-                return;
-            }
-
-            // Now that the current GP is complete, take its callbacks
-            let current_callbacks = state.current_gp.take_callbacks();
-
-            // Check if we need to watch for a next GP
-            if !state.next_callbacks.is_empty() {
-                let callbacks = core::mem::take(&mut state.next_callbacks);
-                state.current_gp.restart(callbacks);
-            } else {
-                self.is_monitoring.store(false, Relaxed);
-            }
-
-            current_callbacks
+            self.has_ready_callbacks.store(false, Relaxed);
+            (events, core::mem::take(&mut state.ready_callbacks))
         };
+        self.handle_events(events);
 
         // Invoke the callbacks to notify the completion of GP
         for f in callbacks {
@@ -73,27 +83,165 @@ impl RcuMonitor {
         }
     }
 
+    /// Passes the quiescent state on the current CPU in the interrupt context.
+    ///
+    /// The callbacks of the completed grace periods are not invoked in the
+    /// interrupt context. They are invoked at the next task switch on any CPU.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the code interrupted on this CPU is not
+    /// executing in a RCU read-side critical section.
+    pub(super) unsafe fn finish_grace_period_in_irq(&self) {
+        // Fast path
+        if !self.is_monitoring.load(Relaxed) {
+            return;
+        }
+
+        let events = {
+            let mut state = self.state.disable_irq().lock();
+            let cpu = state.as_atomic_mode_guard().current_cpu();
+            self.pass_quiescent_state(&mut state, cpu)
+        };
+        self.handle_events(events);
+    }
+
+    /// Marks the current CPU as idle or not.
+    ///
+    /// An idle CPU is woken up when a new grace period starts, so that it can
+    /// pass the quiescent state in [`Self::finish_grace_period`].
+    pub(super) fn set_idle(&self, cpu: CpuId, is_idle: bool) {
+        if is_idle {
+            self.idle_cpus.add(cpu, Relaxed);
+        } else {
+            self.idle_cpus.remove(cpu, Relaxed);
+        }
+        // Pairs with the fence in `handle_events`. Either the CPU sees that a
+        // new grace period is being monitored, or the starter of the grace
+        // period sees that the CPU is idle and wakes it up.
+        fence(SeqCst);
+    }
+
     pub(super) fn after_grace_period<F>(&self, f: F)
     where
         F: FnOnce() + Send + 'static,
     {
-        let mut state = self.state.disable_irq().lock();
+        let events = {
+            let mut state = self.state.disable_irq().lock();
 
-        state.next_callbacks.push_back(Box::new(f));
+            state.next_callbacks.push_back(Box::new(f));
 
+            if !state.current_gp.is_complete() {
+                return;
+            }
+            self.start_grace_period(&mut state)
+        };
+        self.handle_events(events);
+    }
+
+    /// Waits until a grace period that starts after this function is called
+    /// has completed.
+    pub(super) fn wait_for_grace_period(&self) {
+        let (events, target) = {
+            let mut state = self.state.disable_irq().lock();
+
+            if state.current_gp.is_complete() {
+                let events = self.start_grace_period(&mut state);
+                (events, state.nr_started)
+            } else {
+                // The current grace period may have started before the
+                // caller's updates, so wait for the next one.
+                state.is_next_gp_requested = true;
+                (GpEvents::default(), state.nr_started + 1)
+            }
+        };
+        self.handle_events(events);
+
+        // Sleeping switches the current task out, which passes the quiescent
+        // state on the current CPU.
+        self.wait_queue
+            .wait_until(|| (self.nr_completed.load(Acquire) >= target).then_some(()));
+    }
+
+    fn pass_quiescent_state(&self, state: &mut State, cpu: CpuId) -> GpEvents {
+        if state.current_gp.is_complete() {
+            return GpEvents::default();
+        }
+
+        state.current_gp.finish_grace_period(cpu);
         if !state.current_gp.is_complete() {
-            return;
+            return GpEvents::default();
+        }
+
+        // Now that the current GP is complete, take its callbacks
+        let mut current_callbacks = state.current_gp.take_callbacks();
+        state.ready_callbacks.append(&mut current_callbacks);
+        if !state.ready_callbacks.is_empty() {
+            self.has_ready_callbacks.store(true, Relaxed);
         }
+        self.nr_completed.store(state.nr_started, Release);
+
+        // Check if we need to watch for a next GP
+        let mut events = if !state.next_callbacks.is_empty() || state.is_next_gp_requested {
+            self.start_grace_period(state)
+        } else {
+            self.is_monitoring.store(false, Relaxed);
+            GpEvents::default()
+        };
+        events.is_completed = true;
+        events
+    }
 
+    fn start_grace_period(&self, state: &mut State) -> GpEvents {
         let callbacks = core::mem::take(&mut state.next_callbacks);
         state.current_gp.restart(callbacks);
+        state.nr_started += 1;
+        state.is_next_gp_requested = false;
         self.is_monitoring.store(true, Relaxed);
+
+        GpEvents {
+            is_completed: false,
+            is_started: true,
+        }
     }
+
+    fn handle_events(&self, events: GpEvents) {
+        if events.is_completed {
+            self.wait_queue.wake_all();
+        }
+
+        if events.is_started {
+            // Pairs with the fence in `set_idle`.
+            fence(SeqCst);
+            let idle_cpus = self.idle_cpus.load(Relaxed);
+            // The IPIs are not available during the early boot, when all the
+            // other CPUs are not running yet.
+            if !idle_cpus.is_empty()
+                && let Some(ipi_sender) = IPI_SENDER.get()
+            {
+                // The IPI does nothing but wakes up the idle CPUs.
+                ipi_sender.inter_processor_call(&idle_cpus, || {});
+            }
+        }
+    }
+}
+
+/// The events that happen when the state of the grace periods changes.
+#[derive(Default)]
+struct GpEvents {
+    is_completed: bool,
+    is_started: bool,
 }
 
 struct State {
     current_gp: GracePeriod,
     next_callbacks: Callbacks,
+    /// The callbacks of the completed grace periods that are not invoked yet.
+    ready_callbacks: Callbacks,
+    /// Whether a task is waiting for the next grace period.
+    is_next_gp_requested: bool,
+    /// The number of started grace periods.
+    nr_started: u64,
 }
 
 impl State {
@@ -101,6 +249,9 @@ impl State {
         Self {
             current_gp: GracePeriod::new(),
             next_callbacks: VecDeque::new(),
+            ready_callbacks: VecDeque::new(),
+            is_next_gp_requested: false,
+            nr_started: 0,
         }
     }
 }
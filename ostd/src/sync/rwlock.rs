@@ -11,6 +11,8 @@ use core::{
     },
 };
 
+#[cfg(feature = "lockdep")]
+use super::lockdep::{self, LockClassKey, LockKind, LockMode};
 use super::{
     PreemptDisabled,
     guard::{GuardTransfer, SpinGuardian},
@@ -104,6 +106,8 @@ pub struct RwLock<T: ?Sized, Guard = PreemptDisabled> {
     /// - **Bit 61:** Indicates if an upgradeable reader is being upgraded.
     /// - **Bits 60-0:** Reader lock count.
     lock: AtomicUsize,
+    #[cfg(feature = "lockdep")]
+    class: LockClassKey,
     val: UnsafeCell<T>,
 }
 
@@ -130,10 +134,13 @@ const MAX_READER: usize = 1 << (usize::BITS - 4);
 
 impl<T, G> RwLock<T, G> {
     /// Creates a new spin-based read-write lock with an initial value.
+    #[cfg_attr(feature = "lockdep", track_caller)]
     pub const fn new(val: T) -> Self {
         Self {
             guard: PhantomData,
             lock: AtomicUsize::new(0),
+            #[cfg(feature = "lockdep")]
+            class: LockClassKey::new(),
             val: UnsafeCell::new(val),
         }
     }
@@ -146,7 +153,10 @@ impl<T: ?Sized, G: SpinGuardian> RwLock<T, G> {
     /// upgrading upreaders present. There is no guarantee for the order
     /// in which other readers or writers waiting simultaneously will
     /// obtain the lock.
+    #[cfg_attr(feature = "lockdep", track_caller)]
     pub fn read(&self) -> RwLockReadGuard<'_, T, G> {
+        #[cfg(feature = "lockdep")]
+        lockdep::validate_acquire(self.class, LockMode::Read, LockKind::Spinning);
         loop {
             if let Some(readguard) = self.try_read() {
                 return readguard;
@@ -162,7 +172,10 @@ impl<T: ?Sized, G: SpinGuardian> RwLock<T, G> {
     /// upreaders or readers present. There is no guarantee for the order
     /// in which other readers or writers waiting simultaneously will
     /// obtain the lock.
+    #[cfg_attr(feature = "lockdep", track_caller)]
     pub fn write(&self) -> RwLockWriteGuard<'_, T, G> {
+        #[cfg(feature = "lockdep")]
+        lockdep::validate_acquire(self.class, LockMode::Write, LockKind::Spinning);
         loop {
             if let Some(writeguard) = self.try_write() {
                 return writeguard;
@@ -182,7 +195,10 @@ impl<T: ?Sized, G: SpinGuardian> RwLock<T, G> {
     /// and reader do not differ before invoking the upgrade method. However,
     /// only one upreader can exist at any time to avoid deadlock in the
     /// upgrade method.
+    #[cfg_attr(feature = "lockdep", track_caller)]
     pub fn upread(&self) -> RwLockUpgradeableGuard<'_, T, G> {
+        #[cfg(feature = "lockdep")]
+        lockdep::validate_acquire(self.class, LockMode::Write, LockKind::Spinning);
         loop {
             if let Some(guard) = self.try_upread() {
                 return guard;
@@ -199,6 +215,8 @@ impl<T: ?Sized, G: SpinGuardian> RwLock<T, G> {
         let guard = G::read_guard();
         let lock = self.lock.fetch_add(READER, Acquire);
         if lock & (WRITER | MAX_READER | BEING_UPGRADED) == 0 {
+            #[cfg(feature = "lockdep")]
+            lockdep::acquired(self.class, self.addr(), LockMode::Read);
             Some(RwLockReadGuard { inner: self, guard })
         } else {
             self.lock.fetch_sub(READER, Release);
@@ -216,6 +234,8 @@ impl<T: ?Sized, G: SpinGuardian> RwLock<T, G> {
             .compare_exchange(0, WRITER, Acquire, Relaxed)
             .is_ok()
         {
+            #[cfg(feature = "lockdep")]
+            lockdep::acquired(self.class, self.addr(), LockMode::Write);
             Some(RwLockWriteGuard { inner: self, guard })
         } else {
             None
@@ -229,6 +249,8 @@ impl<T: ?Sized, G: SpinGuardian> RwLock<T, G> {
         let guard = G::guard();
         let lock = self.lock.fetch_or(UPGRADEABLE_READER, Acquire) & (WRITER | UPGRADEABLE_READER);
         if lock == 0 {
+            #[cfg(feature = "lockdep")]
+            lockdep::acquired(self.class, self.addr(), LockMode::Write);
             return Some(RwLockUpgradeableGuard { inner: self, guard });
         } else if lock == WRITER {
             self.lock.fetch_sub(UPGRADEABLE_READER, Release);
@@ -251,6 +273,11 @@ impl<T: ?Sized, G: SpinGuardian> RwLock<T, G> {
     pub(super) fn as_ptr(&self) -> *mut T {
         self.val.get()
     }
+
+    #[cfg(feature = "lockdep")]
+    fn addr(&self) -> *const () {
+        core::ptr::from_ref(self).cast()
+    }
 }
 
 impl<T: ?Sized + fmt::Debug, G> fmt::Debug for RwLock<T, G> {
@@ -297,6 +324,8 @@ impl<T: ?Sized, G: SpinGuardian> Deref for RwLockReadGuard<'_, T, G> {
 
 impl<T: ?Sized, G: SpinGuardian> Drop for RwLockReadGuard<'_, T, G> {
     fn drop(&mut self) {
+        #[cfg(feature = "lockdep")]
+        lockdep::released(self.inner.addr());
         self.inner.lock.fetch_sub(READER, Release);
     }
 }
@@ -335,6 +364,8 @@ impl<T: ?Sized, G: SpinGuardian> DerefMut for RwLockWriteGuard<'_, T, G> {
 
 impl<T: ?Sized, G: SpinGuardian> Drop for RwLockWriteGuard<'_, T, G> {
     fn drop(&mut self) {
+        #[cfg(feature = "lockdep")]
+        lockdep::released(self.inner.addr());
         self.inner.lock.fetch_and(!WRITER, Release);
     }
 }
@@ -391,6 +422,8 @@ impl<'a, T: ?Sized, G: SpinGuardian> RwLockUpgradeableGuard<'a, T, G> {
             let inner = self.inner;
             let guard = self.guard.transfer_to();
             drop(self);
+            #[cfg(feature = "lockdep")]
+            lockdep::acquired(inner.class, inner.addr(), LockMode::Write);
             Ok(RwLockWriteGuard { inner, guard })
         } else {
             Err(self)
@@ -408,6 +441,8 @@ impl<T: ?Sized, G: SpinGuardian> Deref for RwLockUpgradeableGuard<'_, T, G> {
 
 impl<T: ?Sized, G: SpinGuardian> Drop for RwLockUpgradeableGuard<'_, T, G> {
     fn drop(&mut self) {
+        #[cfg(feature = "lockdep")]
+        lockdep::released(self.inner.addr());
         self.inner.lock.fetch_sub(UPGRADEABLE_READER, Release);
     }
 }
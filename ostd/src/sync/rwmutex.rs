@@ -11,6 +11,8 @@ use core::{
 };
 
 use super::WaitQueue;
+#[cfg(feature = "lockdep")]
+use super::lockdep::{self, LockClassKey, LockKind, LockMode};
 
 /// A mutex that provides data access to either one writer or many readers.
 ///
@@ -94,6 +96,8 @@ pub struct RwMutex<T: ?Sized> {
     lock: AtomicUsize,
     /// Threads that fail to acquire the mutex will sleep on this waitqueue.
     queue: WaitQueue,
+    #[cfg(feature = "lockdep")]
+    class: LockClassKey,
     val: UnsafeCell<T>,
 }
 
@@ -109,11 +113,14 @@ const MAX_READER: usize = 1 << (usize::BITS - 4);
 
 impl<T> RwMutex<T> {
     /// Creates a new read-write mutex with an initial value.
+    #[cfg_attr(feature = "lockdep", track_caller)]
     pub const fn new(val: T) -> Self {
         Self {
             val: UnsafeCell::new(val),
             lock: AtomicUsize::new(0),
             queue: WaitQueue::new(),
+            #[cfg(feature = "lockdep")]
+            class: LockClassKey::new(),
         }
     }
 }
@@ -127,6 +134,9 @@ impl<T: ?Sized> RwMutex<T> {
     /// will acquire the mutex.
     #[track_caller]
     pub fn read(&self) -> RwMutexReadGuard<'_, T> {
+        #[cfg(feature = "lockdep")]
+        lockdep::validate_acquire(self.class, LockMode::Read, LockKind::Sleeping);
+
         self.queue.wait_until(|| self.try_read())
     }
 
@@ -138,6 +148,9 @@ impl<T: ?Sized> RwMutex<T> {
     /// will acquire the mutex.
     #[track_caller]
     pub fn write(&self) -> RwMutexWriteGuard<'_, T> {
+        #[cfg(feature = "lockdep")]
+        lockdep::validate_acquire(self.class, LockMode::Write, LockKind::Sleeping);
+
         self.queue.wait_until(|| self.try_write())
     }
 
@@ -153,6 +166,9 @@ impl<T: ?Sized> RwMutex<T> {
     /// upgrade method.
     #[track_caller]
     pub fn upread(&self) -> RwMutexUpgradeableGuard<'_, T> {
+        #[cfg(feature = "lockdep")]
+        lockdep::validate_acquire(self.class, LockMode::Write, LockKind::Sleeping);
+
         self.queue.wait_until(|| self.try_upread())
     }
 
@@ -162,6 +178,8 @@ impl<T: ?Sized> RwMutex<T> {
     pub fn try_read(&self) -> Option<RwMutexReadGuard<'_, T>> {
         let lock = self.lock.fetch_add(READER, Acquire);
         if lock & (WRITER | BEING_UPGRADED | MAX_READER) == 0 {
+            #[cfg(feature = "lockdep")]
+            lockdep::acquired(self.class, self.addr(), LockMode::Read);
             Some(RwMutexReadGuard { inner: self })
         } else {
             self.lock.fetch_sub(READER, Release);
@@ -178,6 +196,8 @@ impl<T: ?Sized> RwMutex<T> {
             .compare_exchange(0, WRITER, Acquire, Relaxed)
             .is_ok()
         {
+            #[cfg(feature = "lockdep")]
+            lockdep::acquired(self.class, self.addr(), LockMode::Write);
             Some(RwMutexWriteGuard { inner: self })
         } else {
             None
@@ -190,6 +210,8 @@ impl<T: ?Sized> RwMutex<T> {
     pub fn try_upread(&self) -> Option<RwMutexUpgradeableGuard<'_, T>> {
         let lock = self.lock.fetch_or(UPGRADEABLE_READER, Acquire) & (WRITER | UPGRADEABLE_READER);
         if lock == 0 {
+            #[cfg(feature = "lockdep")]
+            lockdep::acquired(self.class, self.addr(), LockMode::Write);
             return Some(RwMutexUpgradeableGuard { inner: self });
         } else if lock == WRITER {
             self.lock.fetch_sub(UPGRADEABLE_READER, Release);
@@ -204,6 +226,11 @@ impl<T: ?Sized> RwMutex<T> {
     pub fn get_mut(&mut self) -> &mut T {
         self.val.get_mut()
     }
+
+    #[cfg(feature = "lockdep")]
+    fn addr(&self) -> *const () {
+        core::ptr::from_ref(self).cast()
+    }
 }
 
 impl<T: ?Sized + fmt::Debug> fmt::Debug for RwMutex<T> {
@@ -241,6 +268,9 @@ impl<T: ?Sized> Deref for RwMutexReadGuard<'_, T> {
 
 impl<T: ?Sized> Drop for RwMutexReadGuard<'_, T> {
     fn drop(&mut self) {
+        #[cfg(feature = "lockdep")]
+        lockdep::released(self.inner.addr());
+
         // When there are no readers, wake up a waiting writer.
         if self.inner.lock.fetch_sub(READER, Release) == READER {
             self.inner.queue.wake_one();
@@ -286,6 +316,8 @@ impl<'a, T: ?Sized> RwMutexWriteGuard<'a, T> {
             .compare_exchange(WRITER, UPGRADEABLE_READER, AcqRel, Relaxed);
         if res.is_ok() {
             drop(self);
+            #[cfg(feature = "lockdep")]
+            lockdep::acquired(inner.class, inner.addr(), LockMode::Write);
             Ok(RwMutexUpgradeableGuard { inner })
         } else {
             Err(self)
@@ -301,6 +333,9 @@ impl<T: ?Sized> DerefMut for RwMutexWriteGuard<'_, T> {
 
 impl<T: ?Sized> Drop for RwMutexWriteGuard<'_, T> {
     fn drop(&mut self) {
+        #[cfg(feature = "lockdep")]
+        lockdep::released(self.inner.addr());
+
         self.inner.lock.fetch_and(!WRITER, Release);
 
         // When the current writer releases, wake up all the sleeping threads.
@@ -353,6 +388,8 @@ impl<'a, T: ?Sized> RwMutexUpgradeableGuard<'a, T> {
         if res.is_ok() {
             let inner = self.inner;
             drop(self);
+            #[cfg(feature = "lockdep")]
+            lockdep::acquired(inner.class, inner.addr(), LockMode::Write);
             Ok(RwMutexWriteGuard { inner })
         } else {
             Err(self)
@@ -370,6 +407,9 @@ impl<T: ?Sized> Deref for RwMutexUpgradeableGuard<'_, T> {
 
 impl<T: ?Sized> Drop for RwMutexUpgradeableGuard<'_, T> {
     fn drop(&mut self) {
+        #[cfg(feature = "lockdep")]
+        lockdep::released(self.inner.addr());
+
         let res = self.inner.lock.fetch_sub(UPGRADEABLE_READER, Release);
         if res == UPGRADEABLE_READER {
             self.inner.queue.wake_all();
@@ -8,6 +8,8 @@ use core::{
     sync::atomic::{AtomicBool, Ordering},
 };
 
+#[cfg(feature = "lockdep")]
+use super::lockdep::{self, LockClassKey, LockKind, LockMode};
 use super::{LocalIrqDisabled, PreemptDisabled, guard::SpinGuardian};
 use crate::task::atomic_mode::AsAtomicModeGuard;
 
@@ -36,14 +38,19 @@ pub struct SpinLock<T: ?Sized, G = PreemptDisabled> {
 
 struct SpinLockInner<T: ?Sized> {
     lock: AtomicBool,
+    #[cfg(feature = "lockdep")]
+    class: LockClassKey,
     val: UnsafeCell<T>,
 }
 
 impl<T, G> SpinLock<T, G> {
     /// Creates a new spin lock.
+    #[cfg_attr(feature = "lockdep", track_caller)]
     pub const fn new(val: T) -> Self {
         let lock_inner = SpinLockInner {
             lock: AtomicBool::new(false),
+            #[cfg(feature = "lockdep")]
+            class: LockClassKey::new(),
             val: UnsafeCell::new(val),
         };
         Self {
@@ -69,10 +76,15 @@ impl<T: ?Sized> SpinLock<T, PreemptDisabled> {
 
 impl<T: ?Sized, G: SpinGuardian> SpinLock<T, G> {
     /// Acquires the spin lock.
+    #[cfg_attr(feature = "lockdep", track_caller)]
     pub fn lock(&self) -> SpinLockGuard<'_, T, G> {
         // Notice the guard must be created before acquiring the lock.
         let inner_guard = G::guard();
+        #[cfg(feature = "lockdep")]
+        lockdep::validate_acquire(self.inner.class, LockMode::Write, LockKind::Spinning);
         self.acquire_lock();
+        #[cfg(feature = "lockdep")]
+        lockdep::acquired(self.inner.class, self.addr(), LockMode::Write);
         SpinLockGuard {
             lock: self,
             guard: inner_guard,
@@ -83,6 +95,8 @@ impl<T: ?Sized, G: SpinGuardian> SpinLock<T, G> {
     pub fn try_lock(&self) -> Option<SpinLockGuard<'_, T, G>> {
         let inner_guard = G::guard();
         if self.try_acquire_lock() {
+            #[cfg(feature = "lockdep")]
+            lockdep::acquired(self.inner.class, self.addr(), LockMode::Write);
             let lock_guard = SpinLockGuard {
                 lock: self,
                 guard: inner_guard,
@@ -115,8 +129,16 @@ impl<T: ?Sized, G: SpinGuardian> SpinLock<T, G> {
     }
 
     fn release_lock(&self) {
+        #[cfg(feature = "lockdep")]
+        lockdep::released(self.addr());
         self.inner.lock.store(false, Ordering::Release);
     }
+
+    /// Returns the address of the lock, which is the same for all the guard behaviors.
+    #[cfg(feature = "lockdep")]
+    fn addr(&self) -> *const () {
+        core::ptr::from_ref(self).cast()
+    }
 }
 
 impl<T: ?Sized + fmt::Debug, G> fmt::Debug for SpinLock<T, G> {
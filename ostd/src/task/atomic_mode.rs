@@ -39,6 +39,8 @@ pub fn might_sleep() {
     if (preempt_count != 0 || !is_local_irq_enabled)
         && !crate::IN_BOOTSTRAP_CONTEXT.load(Ordering::Relaxed)
     {
+        #[cfg(feature = "lockdep")]
+        crate::sync::lockdep::print_held_locks();
         panic!(
             "This function might break atomic mode (preempt_count = {}, is_local_irq_enabled = {})",
             preempt_count, is_local_irq_enabled
@@ -46,6 +48,14 @@ pub fn might_sleep() {
     }
 }
 
+/// Returns whether the current CPU is executing in atomic mode, where sleeping is not allowed.
+///
+/// It is always `false` in the bootstrap context, like [`might_sleep`].
+pub(crate) fn is_in_atomic_mode() -> bool {
+    (super::preempt::cpu_local::get_guard_count() != 0 || !crate::arch::irq::is_local_enabled())
+        && !crate::IN_BOOTSTRAP_CONTEXT.load(Ordering::Relaxed)
+}
+
 /// A marker trait for guard types that enforce the atomic mode.
 ///
 /// Key kernel primitives such as `SpinLock` and `Rcu` rely on
@@ -23,6 +23,7 @@ use processor::current_task;
 use spin::Once;
 use utils::ForceSync;
 
+pub(crate) use self::preempt::is_preempt_disabled;
 pub use self::{
     preempt::{DisabledPreemptGuard, disable_preempt, halt_cpu},
     scheduler::info::{AtomicCpuId, TaskScheduleInfo},
@@ -67,6 +68,10 @@ pub struct Task {
     switched_to_cpu: AtomicBool,
 
     schedule_info: TaskScheduleInfo,
+
+    /// The locks held by the task, which are recorded by the lock dependency validator.
+    #[cfg(feature = "lockdep")]
+    held_locks: crate::sync::lockdep::HeldLocks,
 }
 
 impl Task {
@@ -84,6 +89,11 @@ impl Task {
         &self.ctx
     }
 
+    #[cfg(feature = "lockdep")]
+    pub(crate) fn held_locks(&self) -> &crate::sync::lockdep::HeldLocks {
+        &self.held_locks
+    }
+
     /// Yields execution so that another task may be scheduled.
     ///
     /// Note that this method cannot be simply named "yield" as the name is
@@ -222,6 +232,8 @@ impl TaskOptions {
                 cpu: AtomicCpuId::default(),
             },
             switched_to_cpu: AtomicBool::new(false),
+            #[cfg(feature = "lockdep")]
+            held_locks: crate::sync::lockdep::HeldLocks::new(),
         };
 
         Ok(new_task)
@@ -5,6 +5,11 @@ mod guard;
 
 pub use self::guard::{DisabledPreemptGuard, disable_preempt};
 
+/// Returns whether preemption is disabled on the current CPU.
+pub(crate) fn is_preempt_disabled() -> bool {
+    cpu_local::get_guard_count() != 0
+}
+
 /// Halts the CPU until interrupts if no preemption is required.
 ///
 /// This function will return if:
@@ -15,6 +20,10 @@ pub use self::guard::{DisabledPreemptGuard, disable_preempt};
 /// This function will perform preemption before returning if
 /// preemption is required.
 ///
+/// The periodic timer tick is stopped while the CPU is halted, if the timer hardware supports
+/// it. The CPU is woken up by one-shot timer events (see [`crate::timer::set_next_event`])
+/// instead.
+///
 /// # Panics
 ///
 /// This function will panic if it is called in the atomic mode
@@ -23,15 +32,23 @@ pub use self::guard::{DisabledPreemptGuard, disable_preempt};
 pub fn halt_cpu() {
     crate::task::atomic_mode::might_sleep();
 
+    // Halted CPUs do not switch tasks, so they should be woken up to pass the
+    // quiescent states of RCU grace periods.
+    crate::sync::rcu_enter_idle();
+
     let irq_guard = crate::irq::disable_local();
 
     if cpu_local::need_preempt() {
         drop(irq_guard);
     } else {
+        crate::timer::stop_tick();
         core::mem::forget(irq_guard);
         // IRQs were previously enabled (checked by `might_sleep`). So we can re-enable them now.
         crate::arch::irq::enable_local_and_halt();
+        crate::timer::restart_tick();
     }
 
+    crate::sync::rcu_exit_idle();
+
     super::scheduler::might_preempt();
 }
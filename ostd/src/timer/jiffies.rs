@@ -1,11 +1,11 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use core::{
-    sync::atomic::{AtomicU64, Ordering},
-    time::Duration,
-};
+use core::time::Duration;
+
+use spin::Once;
 
 use super::TIMER_FREQ;
+use crate::arch::{read_tsc, tsc_freq};
 
 /// Jiffies is a term used to denote the units of time measurement by the kernel.
 ///
@@ -14,7 +14,16 @@ use super::TIMER_FREQ;
 #[derive(Copy, Clone, Debug)]
 pub struct Jiffies(u64);
 
-pub(crate) static ELAPSED: AtomicU64 = AtomicU64::new(0);
+/// The TSC value when the jiffies start counting.
+///
+/// Since the tick may be stopped on idle CPUs, the elapsed jiffies are calculated from the TSC
+/// instead of being counted on timer interrupts.
+static START_TSC: Once<u64> = Once::new();
+
+/// Starts counting the jiffies.
+pub(super) fn init() {
+    START_TSC.call_once(read_tsc);
+}
 
 impl Jiffies {
     /// The maximum value of [`Jiffies`].
@@ -27,7 +36,13 @@ impl Jiffies {
 
     /// Returns the elapsed time since the system boots up.
     pub fn elapsed() -> Self {
-        Self::new(ELAPSED.load(Ordering::Relaxed))
+        let Some(start_tsc) = START_TSC.get() else {
+            return Self::new(0);
+        };
+
+        let elapsed_tsc = read_tsc().saturating_sub(*start_tsc);
+        let elapsed = elapsed_tsc as u128 * TIMER_FREQ as u128 / tsc_freq() as u128;
+        Self::new(elapsed as u64)
     }
 
     /// Gets the number of jiffies.
@@ -5,14 +5,19 @@
 mod jiffies;
 
 use alloc::{boxed::Box, vec::Vec};
-use core::{cell::RefCell, sync::atomic::Ordering};
+use core::{
+    cell::RefCell,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
 
 pub use jiffies::Jiffies;
+use spin::Once;
 
 use crate::{
-    arch::trap::TrapFrame,
-    cpu::{CpuId, PinCurrentCpu},
-    cpu_local, irq,
+    arch::{self, trap::TrapFrame},
+    cpu::CpuId,
+    cpu_local, cpu_local_cell, irq,
 };
 
 /// The timer frequency in Hz.
@@ -42,16 +47,150 @@ where
         .push(Box::new(func));
 }
 
+/// Initializes the timer support on the BSP.
+pub(crate) fn init() {
+    jiffies::init();
+}
+
 pub(crate) fn call_timer_callback_functions(_: &TrapFrame) {
     let irq_guard = irq::disable_local();
 
-    if irq_guard.current_cpu() == CpuId::bsp() {
-        jiffies::ELAPSED.fetch_add(1, Ordering::Relaxed);
-    }
-
     let callbacks_guard = INTERRUPT_CALLBACKS.get_with(&irq_guard);
     for callback in callbacks_guard.borrow().iter() {
         (callback)();
     }
     drop(callbacks_guard);
+
+    crate::sync::rcu_on_timer_tick();
+}
+
+static EVENT_HANDLER: Once<fn()> = Once::new();
+
+cpu_local_cell! {
+    /// The TSC value at which the next one-shot timer event expires on this CPU.
+    ///
+    /// The value is `u64::MAX` if there are no pending events.
+    static EVENT_DEADLINE: u64 = u64::MAX;
+}
+
+/// Registers the function that will be executed when a one-shot timer event expires.
+///
+/// The function is executed in the interrupt context on the CPU where the event is set. Only the
+/// first registered function takes effect.
+pub fn register_event_handler(handler: fn()) {
+    EVENT_HANDLER.call_once(|| handler);
+}
+
+/// Sets a one-shot timer event that expires after `delay` on the current CPU.
+///
+/// Each CPU keeps at most one pending event. If an earlier event is pending, this function does
+/// nothing, since the handler of the earlier event is expected to set the next event.
+///
+/// Unlike the timer callbacks registered with [`register_callback_on_cpu`], which are executed
+/// on every timer tick, the event is not bound to the tick granularity if the timer hardware
+/// supports one-shot deadlines. Otherwise, the event expires at the first tick after the delay.
+pub fn set_next_event(delay: Duration) {
+    let _irq_guard = irq::disable_local();
+
+    let delay_in_tsc = delay.as_nanos() * arch::tsc_freq() as u128 / 1_000_000_000;
+    let deadline = arch::read_tsc().saturating_add(delay_in_tsc.try_into().unwrap_or(u64::MAX));
+    if deadline >= EVENT_DEADLINE.load() {
+        return;
+    }
+
+    EVENT_DEADLINE.store(deadline);
+    arch::timer::program_next_interrupt();
+}
+
+/// Returns the TSC value at which the next timer interrupt should be raised on the current CPU.
+///
+/// The next timer interrupt is raised at the next tick or the next one-shot timer event,
+/// whichever is earlier. If the tick is stopped, the next tick is ignored, but the interrupt is
+/// still raised within [`MAX_TICKLESS_DURATION`].
+///
+/// The caller should disable local IRQs.
+pub(crate) fn next_interrupt_deadline(tick_deadline: u64) -> u64 {
+    let event_deadline = EVENT_DEADLINE.load();
+    if !IS_TICK_STOPPED.load() {
+        return tick_deadline.min(event_deadline);
+    }
+
+    let max_tickless_tsc = MAX_TICKLESS_DURATION.as_millis() as u64 * arch::tsc_freq() / 1000;
+    event_deadline.min(arch::read_tsc().saturating_add(max_tickless_tsc))
+}
+
+/// Executes the handler of the one-shot timer event if the event on the current CPU expires.
+///
+/// This function should be called in the timer interrupt.
+pub(crate) fn expire_event() {
+    if EVENT_DEADLINE.load() > arch::read_tsc() {
+        return;
+    }
+
+    EVENT_DEADLINE.store(u64::MAX);
+    if let Some(handler) = EVENT_HANDLER.get() {
+        handler();
+    }
+}
+
+/// The maximum duration for which the tick can be stopped.
+///
+/// Some work is still done at ticks even if the CPU is idle, e.g., the clock sources must be
+/// updated before their counters overflow. So an idle CPU wakes up once per this duration.
+const MAX_TICKLESS_DURATION: Duration = Duration::from_secs(1);
+
+cpu_local_cell! {
+    /// Whether the tick is stopped on this CPU.
+    static IS_TICK_STOPPED: bool = false;
+}
+
+cpu_local! {
+    /// The number of ticks that are skipped on this CPU because the tick is stopped.
+    static SKIPPED_TICKS: AtomicU64 = AtomicU64::new(0);
+}
+
+/// Returns whether the tick is stopped on the current CPU.
+///
+/// If so, the timer interrupt is raised only for one-shot timer events, and the timer callbacks
+/// should not be executed.
+pub(crate) fn is_tick_stopped() -> bool {
+    IS_TICK_STOPPED.load()
+}
+
+/// Stops the tick on the current CPU before the CPU is halted.
+///
+/// The caller should disable local IRQs.
+pub(crate) fn stop_tick() {
+    if !arch::timer::can_stop_tick() {
+        return;
+    }
+
+    IS_TICK_STOPPED.store(true);
+    arch::timer::program_next_interrupt();
+}
+
+/// Restarts the tick on the current CPU after the CPU wakes up.
+pub(crate) fn restart_tick() {
+    let irq_guard = irq::disable_local();
+
+    if !IS_TICK_STOPPED.load() {
+        return;
+    }
+
+    IS_TICK_STOPPED.store(false);
+    let skipped_ticks = arch::timer::restart_tick();
+    SKIPPED_TICKS
+        .get_with(&irq_guard)
+        .fetch_add(skipped_ticks, Ordering::Relaxed);
+}
+
+/// Returns the number of ticks that have been skipped on the given CPU.
+///
+/// The tick is stopped while the CPU is halted by [`halt_cpu`], during which the timer callbacks
+/// registered with [`register_callback_on_cpu`] are not executed. Since the CPU does nothing
+/// during the period, the skipped ticks can be accounted as idle time.
+///
+/// [`halt_cpu`]: crate::task::halt_cpu
+pub fn skipped_ticks_on_cpu(cpu: CpuId) -> Jiffies {
+    Jiffies::new(SKIPPED_TICKS.get_on_cpu(cpu).load(Ordering::Relaxed))
 }
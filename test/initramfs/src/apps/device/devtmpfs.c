@@ -0,0 +1,87 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <errno.h>
+#include <sched.h>
+#include <string.h>
+#include <sys/mount.h>
+#include <sys/stat.h>
+#include <sys/statfs.h>
+#include <sys/sysmacros.h>
+#include <unistd.h>
+
+#include "../common/test.h"
+
+#define TMPFS_MAGIC 0x01021994
+#define DEVTMPFS_DIR "/tmp/devtmpfs"
+
+static int check_symlink(const char *path, const char *target)
+{
+	char buf[64];
+	ssize_t len = readlink(path, buf, sizeof(buf) - 1);
+
+	if (len < 0)
+		return -1;
+	buf[len] = '\0';
+	return strcmp(buf, target) == 0;
+}
+
+static int check_char_device(const char *path, unsigned int major,
+			     unsigned int minor)
+{
+	struct stat st;
+
+	if (stat(path, &st) < 0)
+		return -1;
+	return S_ISCHR(st.st_mode) && st.st_rdev == makedev(major, minor);
+}
+
+FN_TEST(dev_is_devtmpfs)
+{
+	struct statfs sfs;
+
+	TEST_RES(statfs("/dev", &sfs), sfs.f_type == TMPFS_MAGIC);
+}
+END_TEST()
+
+FN_TEST(device_nodes)
+{
+	TEST_RES(check_char_device("/dev/null", 1, 3), _ret == 1);
+	TEST_RES(check_char_device("/dev/zero", 1, 5), _ret == 1);
+	TEST_RES(check_char_device("/dev/full", 1, 7), _ret == 1);
+	TEST_RES(check_char_device("/dev/tty", 5, 0), _ret == 1);
+	TEST_RES(check_char_device("/dev/console", 5, 1), _ret == 1);
+}
+END_TEST()
+
+FN_TEST(standard_symlinks)
+{
+	TEST_RES(check_symlink("/dev/fd", "/proc/self/fd"), _ret == 1);
+	TEST_RES(check_symlink("/dev/stdin", "/proc/self/fd/0"), _ret == 1);
+	TEST_RES(check_symlink("/dev/stdout", "/proc/self/fd/1"), _ret == 1);
+	TEST_RES(check_symlink("/dev/stderr", "/proc/self/fd/2"), _ret == 1);
+	TEST_RES(check_symlink("/dev/ptmx", "pts/ptmx"), _ret == 1);
+}
+END_TEST()
+
+FN_TEST(mount_again)
+{
+	TEST_SUCC(unshare(CLONE_NEWNS));
+	TEST_RES(mkdir(DEVTMPFS_DIR, 0755), _ret == 0 || errno == EEXIST);
+
+	// All the mounts of devtmpfs share the same instance.
+	TEST_SUCC(mount("devtmpfs", DEVTMPFS_DIR, "devtmpfs", 0, NULL));
+	TEST_RES(check_char_device(DEVTMPFS_DIR "/null", 1, 3), _ret == 1);
+	TEST_RES(check_symlink(DEVTMPFS_DIR "/fd", "/proc/self/fd"),
+		 _ret == 1);
+
+	TEST_SUCC(mkdir(DEVTMPFS_DIR "/test_dir", 0755));
+	TEST_RES(access("/dev/test_dir", F_OK), _ret == 0);
+	TEST_SUCC(rmdir("/dev/test_dir"));
+	TEST_ERRNO(access(DEVTMPFS_DIR "/test_dir", F_OK), ENOENT);
+
+	TEST_SUCC(umount(DEVTMPFS_DIR));
+	TEST_SUCC(rmdir(DEVTMPFS_DIR));
+}
+END_TEST()
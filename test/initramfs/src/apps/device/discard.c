@@ -0,0 +1,311 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+#include <fcntl.h>
+#include <linux/fs.h>
+#include <linux/loop.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/ioctl.h>
+#include <sys/mount.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "../common/test.h"
+
+#define IMAGE_PATH "/tmp/discard_test.img"
+#define MOUNT_PATH "/tmp/discard_test_mnt"
+#define SECTOR_SIZE 512
+
+#define EXT2_BLOCK_SIZE 4096
+#define NR_BLOCKS 64
+#define NR_INODES 32
+#define IMAGE_SIZE (NR_BLOCKS * EXT2_BLOCK_SIZE)
+
+// The layout of the Ext2 image: the superblock, the group descriptor table,
+// the block bitmap, the inode bitmap, the inode table and the root directory.
+#define GDT_BLOCK 1
+#define BLOCK_BITMAP_BLOCK 2
+#define INODE_BITMAP_BLOCK 3
+#define INODE_TABLE_BLOCK 4
+#define ROOT_DIR_BLOCK 5
+#define NR_USED_BLOCKS 6
+#define NR_FREE_BLOCKS (NR_BLOCKS - NR_USED_BLOCKS)
+#define FIRST_INO 11
+
+static int image_fd;
+static int loop_fd;
+static char loop_path[32];
+
+static uint8_t block[EXT2_BLOCK_SIZE];
+
+static void put16(uint8_t *buf, size_t offset, uint16_t value)
+{
+	memcpy(&buf[offset], &value, sizeof(value));
+}
+
+static void put32(uint8_t *buf, size_t offset, uint32_t value)
+{
+	memcpy(&buf[offset], &value, sizeof(value));
+}
+
+static void set_bits(uint8_t *bitmap, size_t start, size_t end)
+{
+	for (size_t i = start; i < end; i++)
+		bitmap[i / 8] |= 1 << (i % 8);
+}
+
+static void write_block(int fd, uint32_t bid)
+{
+	CHECK_WITH(pwrite(fd, block, EXT2_BLOCK_SIZE,
+			  (off_t)bid * EXT2_BLOCK_SIZE),
+		   _ret == EXT2_BLOCK_SIZE);
+	memset(block, 0, sizeof(block));
+}
+
+// Writes an Ext2 image with a single block group and an empty root directory.
+static void write_ext2(int fd)
+{
+	uint8_t *sb = &block[1024];
+	uint8_t *root = &block[128];
+
+	put32(sb, 0, NR_INODES);
+	put32(sb, 4, NR_BLOCKS);
+	put32(sb, 12, NR_FREE_BLOCKS);
+	put32(sb, 16, NR_INODES - FIRST_INO);
+	put32(sb, 24, 2); // log2(EXT2_BLOCK_SIZE / 1024)
+	put32(sb, 28, 2);
+	put32(sb, 32, EXT2_BLOCK_SIZE * 8);
+	put32(sb, 36, EXT2_BLOCK_SIZE * 8);
+	put32(sb, 40, NR_INODES);
+	put16(sb, 54, -1);
+	put16(sb, 56, 0xef53);
+	put16(sb, 58, 1); // Cleanly unmounted
+	put16(sb, 60, 1); // Continue on errors
+	put32(sb, 76, 1); // Dynamic revision
+	put32(sb, 84, FIRST_INO);
+	put16(sb, 88, 128);
+	write_block(fd, 0);
+
+	put32(block, 0, BLOCK_BITMAP_BLOCK);
+	put32(block, 4, INODE_BITMAP_BLOCK);
+	put32(block, 8, INODE_TABLE_BLOCK);
+	put16(block, 12, NR_FREE_BLOCKS);
+	put16(block, 14, NR_INODES - FIRST_INO);
+	put16(block, 16, 1);
+	write_block(fd, GDT_BLOCK);
+
+	// The bits beyond the end of the group are marked as used.
+	set_bits(block, 0, NR_USED_BLOCKS);
+	set_bits(block, NR_BLOCKS, EXT2_BLOCK_SIZE * 8);
+	write_block(fd, BLOCK_BITMAP_BLOCK);
+
+	set_bits(block, 0, FIRST_INO);
+	set_bits(block, NR_INODES, EXT2_BLOCK_SIZE * 8);
+	write_block(fd, INODE_BITMAP_BLOCK);
+
+	// The root directory is the second inode.
+	put16(root, 0, S_IFDIR | 0755);
+	put32(root, 4, EXT2_BLOCK_SIZE);
+	put16(root, 26, 2);
+	put32(root, 28, EXT2_BLOCK_SIZE / SECTOR_SIZE);
+	put32(root, 40, ROOT_DIR_BLOCK);
+	write_block(fd, INODE_TABLE_BLOCK);
+
+	put32(block, 0, 2);
+	put16(block, 4, 12);
+	block[6] = 1;
+	block[7] = 2;
+	block[8] = '.';
+	put32(block, 12, 2);
+	put16(block, 16, EXT2_BLOCK_SIZE - 12);
+	block[18] = 2;
+	block[19] = 2;
+	memcpy(&block[20], "..", 2);
+	write_block(fd, ROOT_DIR_BLOCK);
+}
+
+FN_SETUP(loop)
+{
+	struct loop_config config = { 0 };
+	int ctl_fd, loop_index;
+
+	image_fd = CHECK(open(IMAGE_PATH, O_CREAT | O_RDWR | O_TRUNC, 0600));
+	CHECK(ftruncate(image_fd, IMAGE_SIZE));
+	write_ext2(image_fd);
+
+	ctl_fd = CHECK(open("/dev/loop-control", O_RDWR));
+	loop_index = CHECK(ioctl(ctl_fd, LOOP_CTL_GET_FREE));
+	CHECK(close(ctl_fd));
+
+	snprintf(loop_path, sizeof(loop_path), "/dev/loop%d", loop_index);
+	loop_fd = CHECK(open(loop_path, O_RDWR));
+	config.fd = image_fd;
+	CHECK(ioctl(loop_fd, LOOP_CONFIGURE, &config));
+}
+END_SETUP()
+
+FN_TEST(blkdiscard_invalid)
+{
+	uint64_t range[2];
+
+	range[0] = 1;
+	range[1] = SECTOR_SIZE;
+	TEST_ERRNO(ioctl(loop_fd, BLKDISCARD, range), EINVAL);
+
+	range[0] = 0;
+	range[1] = SECTOR_SIZE + 1;
+	TEST_ERRNO(ioctl(loop_fd, BLKDISCARD, range), EINVAL);
+
+	range[0] = IMAGE_SIZE - SECTOR_SIZE;
+	range[1] = 2 * SECTOR_SIZE;
+	TEST_ERRNO(ioctl(loop_fd, BLKDISCARD, range), EINVAL);
+
+	range[0] = IMAGE_SIZE;
+	range[1] = SECTOR_SIZE;
+	TEST_ERRNO(ioctl(loop_fd, BLKDISCARD, range), EINVAL);
+
+	range[0] = SECTOR_SIZE;
+	range[1] = UINT64_MAX - SECTOR_SIZE + 1;
+	TEST_ERRNO(ioctl(loop_fd, BLKDISCARD, range), EINVAL);
+
+	TEST_ERRNO(ioctl(loop_fd, BLKDISCARD, NULL), EFAULT);
+}
+END_TEST()
+
+FN_TEST(blkdiscard)
+{
+	uint64_t range[2];
+	char buf[SECTOR_SIZE];
+	char zeros[SECTOR_SIZE] = { 0 };
+
+	// The last block of the image is free, so it can be overwritten.
+	memset(buf, 'x', sizeof(buf));
+	TEST_RES(pwrite(loop_fd, buf, SECTOR_SIZE, IMAGE_SIZE - SECTOR_SIZE),
+		 _ret == SECTOR_SIZE);
+	TEST_SUCC(fsync(loop_fd));
+
+	range[0] = IMAGE_SIZE - SECTOR_SIZE;
+	range[1] = SECTOR_SIZE;
+	TEST_SUCC(ioctl(loop_fd, BLKDISCARD, range));
+	TEST_RES(pread(loop_fd, buf, SECTOR_SIZE, IMAGE_SIZE - SECTOR_SIZE),
+		 _ret == SECTOR_SIZE && memcmp(buf, zeros, SECTOR_SIZE) == 0);
+}
+END_TEST()
+
+FN_SETUP(mount)
+{
+	CHECK(mkdir(MOUNT_PATH, 0755));
+	CHECK(mount(loop_path, MOUNT_PATH, "ext2", 0, NULL));
+}
+END_SETUP()
+
+FN_TEST(fitrim_invalid)
+{
+	struct fstrim_range range;
+	int fd;
+
+	fd = TEST_SUCC(open(MOUNT_PATH, O_RDONLY | O_DIRECTORY));
+
+	// The range is shorter than a block.
+	range.start = 0;
+	range.len = EXT2_BLOCK_SIZE - 1;
+	range.minlen = 0;
+	TEST_ERRNO(ioctl(fd, FITRIM, &range), EINVAL);
+
+	// The range starts beyond the file system.
+	range.start = IMAGE_SIZE;
+	range.len = EXT2_BLOCK_SIZE;
+	range.minlen = 0;
+	TEST_ERRNO(ioctl(fd, FITRIM, &range), EINVAL);
+
+	// The minimum length exceeds a block group.
+	range.start = 0;
+	range.len = UINT64_MAX;
+	range.minlen = (uint64_t)EXT2_BLOCK_SIZE * EXT2_BLOCK_SIZE * 8 + 1;
+	TEST_ERRNO(ioctl(fd, FITRIM, &range), EINVAL);
+
+	TEST_ERRNO(ioctl(fd, FITRIM, NULL), EFAULT);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(fitrim_unsupported)
+{
+	struct fstrim_range range = { .start = 0, .len = UINT64_MAX };
+	int fd;
+
+	fd = TEST_SUCC(open("/tmp", O_RDONLY | O_DIRECTORY));
+	TEST_ERRNO(ioctl(fd, FITRIM, &range), ENOTTY);
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(fitrim_unprivileged)
+{
+	struct fstrim_range range = { .start = 0, .len = UINT64_MAX };
+	int fd, status;
+	pid_t pid;
+
+	fd = TEST_SUCC(open(MOUNT_PATH, O_RDONLY | O_DIRECTORY));
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		CHECK(setresuid(65534, 65534, 65534));
+		if (ioctl(fd, FITRIM, &range) == 0 || errno != EPERM)
+			_exit(EXIT_FAILURE);
+		_exit(EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(fitrim)
+{
+	struct fstrim_range range;
+	int fd;
+
+	fd = TEST_SUCC(open(MOUNT_PATH, O_RDONLY | O_DIRECTORY));
+
+	// All the free blocks are discarded.
+	range.start = 0;
+	range.len = UINT64_MAX;
+	range.minlen = 0;
+	TEST_RES(ioctl(fd, FITRIM, &range),
+		 _ret == 0 && range.len > 0 &&
+			 range.len <= NR_FREE_BLOCKS * EXT2_BLOCK_SIZE);
+
+	// No free extents are long enough.
+	range.start = 0;
+	range.len = UINT64_MAX;
+	range.minlen = IMAGE_SIZE;
+	TEST_RES(ioctl(fd, FITRIM, &range), _ret == 0 && range.len == 0);
+
+	// Only the free blocks in the range are discarded.
+	range.start = NR_BLOCKS / 2 * EXT2_BLOCK_SIZE;
+	range.len = EXT2_BLOCK_SIZE;
+	range.minlen = 0;
+	TEST_RES(ioctl(fd, FITRIM, &range),
+		 _ret == 0 && range.len == EXT2_BLOCK_SIZE);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(umount(MOUNT_PATH));
+	CHECK(rmdir(MOUNT_PATH));
+	CHECK(ioctl(loop_fd, LOOP_CLR_FD));
+	CHECK(close(loop_fd));
+	CHECK(close(image_fd));
+	CHECK(unlink(IMAGE_PATH));
+}
+END_SETUP()
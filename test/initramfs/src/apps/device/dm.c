@@ -0,0 +1,226 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+#include <fcntl.h>
+#include <linux/dm-ioctl.h>
+#include <linux/fs.h>
+#include <linux/loop.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/ioctl.h>
+#include <sys/stat.h>
+#include <sys/sysmacros.h>
+#include <unistd.h>
+
+#include "../common/test.h"
+
+#define IMAGE_PATH "/tmp/dm_test.img"
+#define IMAGE_SIZE (1024 * 1024)
+#define SECTOR_SIZE 512
+
+#define DM_NAME "dm_test"
+#define LINEAR_OFFSET 8
+#define CRYPT_OFFSET 128
+#define NR_SECTORS 64
+
+#define CRYPT_KEY                                                          \
+	"000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f" \
+	"202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f"
+
+struct dm_buf {
+	struct dm_ioctl io;
+	char data[4096];
+};
+
+static int ctl_fd;
+static int image_fd;
+static int loop_fd;
+static char loop_path[32];
+static unsigned int loop_minor;
+static char dm_path[32];
+
+static void init_buf(struct dm_buf *buf, const char *name)
+{
+	memset(buf, 0, sizeof(*buf));
+	buf->io.version[0] = DM_VERSION_MAJOR;
+	buf->io.data_size = sizeof(*buf);
+	buf->io.data_start = sizeof(struct dm_ioctl);
+	if (name != NULL)
+		strcpy(buf->io.name, name);
+}
+
+static int dm_ioctl(unsigned long cmd, const char *name, uint32_t flags)
+{
+	struct dm_buf buf;
+
+	init_buf(&buf, name);
+	buf.io.flags = flags;
+	return ioctl(ctl_fd, cmd, &buf);
+}
+
+static int load_table(const char *type, uint64_t start, const char *params)
+{
+	struct dm_buf buf;
+	struct dm_target_spec *spec = (struct dm_target_spec *)buf.data;
+
+	init_buf(&buf, DM_NAME);
+	buf.io.target_count = 1;
+	spec->sector_start = start;
+	spec->length = NR_SECTORS;
+	strcpy(spec->target_type, type);
+	strcpy((char *)(spec + 1), params);
+	return ioctl(ctl_fd, DM_TABLE_LOAD, &buf);
+}
+
+FN_SETUP(loop)
+{
+	struct loop_config config = { 0 };
+	int loop_ctl_fd;
+	int index;
+
+	image_fd = CHECK(open(IMAGE_PATH, O_CREAT | O_RDWR | O_TRUNC, 0600));
+	CHECK(ftruncate(image_fd, IMAGE_SIZE));
+	CHECK_WITH(pwrite(image_fd, "linear", 6, LINEAR_OFFSET * SECTOR_SIZE),
+		   _ret == 6);
+
+	loop_ctl_fd = CHECK(open("/dev/loop-control", O_RDWR));
+	index = CHECK(ioctl(loop_ctl_fd, LOOP_CTL_GET_FREE));
+	CHECK(close(loop_ctl_fd));
+
+	snprintf(loop_path, sizeof(loop_path), "/dev/loop%d", index);
+	loop_fd = CHECK(open(loop_path, O_RDWR));
+	config.fd = image_fd;
+	CHECK(ioctl(loop_fd, LOOP_CONFIGURE, &config));
+	loop_minor = index;
+}
+END_SETUP()
+
+FN_SETUP(control)
+{
+	struct stat stat_buf;
+
+	ctl_fd = CHECK(open("/dev/mapper/control", O_RDWR));
+	CHECK_WITH(fstat(ctl_fd, &stat_buf),
+		   S_ISCHR(stat_buf.st_mode) &&
+			   stat_buf.st_rdev == makedev(10, 236));
+}
+END_SETUP()
+
+FN_TEST(version)
+{
+	struct dm_buf buf;
+
+	init_buf(&buf, NULL);
+	TEST_RES(ioctl(ctl_fd, DM_VERSION, &buf),
+		 buf.io.version[0] == DM_VERSION_MAJOR);
+
+	init_buf(&buf, NULL);
+	buf.io.version[0] = DM_VERSION_MAJOR + 1;
+	TEST_ERRNO(ioctl(ctl_fd, DM_VERSION, &buf), EINVAL);
+}
+END_TEST()
+
+FN_TEST(create)
+{
+	struct dm_buf buf;
+
+	init_buf(&buf, DM_NAME);
+	TEST_RES(ioctl(ctl_fd, DM_DEV_CREATE, &buf),
+		 !(buf.io.flags & DM_ACTIVE_PRESENT_FLAG));
+	snprintf(dm_path, sizeof(dm_path), "/dev/dm-%u",
+		 minor(buf.io.dev));
+	TEST_SUCC(access(dm_path, F_OK));
+
+	TEST_ERRNO(dm_ioctl(DM_DEV_CREATE, DM_NAME, 0), EBUSY);
+	TEST_ERRNO(dm_ioctl(DM_DEV_CREATE, "", 0), EINVAL);
+	TEST_ERRNO(dm_ioctl(DM_DEV_STATUS, "dm_nonexistent", 0), ENXIO);
+}
+END_TEST()
+
+FN_TEST(linear)
+{
+	struct dm_buf buf;
+	struct dm_target_spec *spec = (struct dm_target_spec *)buf.data;
+	char params[64];
+	char data[16];
+	uint64_t size;
+	int fd;
+
+	snprintf(params, sizeof(params), "%s %d", loop_path, LINEAR_OFFSET);
+	TEST_ERRNO(load_table("linear", 1, params), EINVAL);
+	TEST_ERRNO(load_table("nonexistent", 0, params), EINVAL);
+	TEST_SUCC(load_table("linear", 0, params));
+
+	init_buf(&buf, DM_NAME);
+	TEST_RES(ioctl(ctl_fd, DM_DEV_STATUS, &buf),
+		 (buf.io.flags & DM_INACTIVE_PRESENT_FLAG) &&
+			 !(buf.io.flags & DM_ACTIVE_PRESENT_FLAG));
+	TEST_SUCC(dm_ioctl(DM_DEV_SUSPEND, DM_NAME, 0));
+
+	init_buf(&buf, DM_NAME);
+	buf.io.flags = DM_STATUS_TABLE_FLAG;
+	snprintf(params, sizeof(params), "7:%u %d", loop_minor, LINEAR_OFFSET);
+	TEST_RES(ioctl(ctl_fd, DM_TABLE_STATUS, &buf),
+		 (buf.io.flags & DM_ACTIVE_PRESENT_FLAG) &&
+			 buf.io.target_count == 1 &&
+			 spec->length == NR_SECTORS &&
+			 strcmp(spec->target_type, "linear") == 0 &&
+			 strcmp((char *)(spec + 1), params) == 0);
+
+	fd = TEST_SUCC(open(dm_path, O_RDWR));
+	TEST_RES(ioctl(fd, BLKGETSIZE64, &size),
+		 size == NR_SECTORS * SECTOR_SIZE);
+	TEST_RES(pread(fd, data, 6, 0),
+		 _ret == 6 && memcmp(data, "linear", 6) == 0);
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(crypt)
+{
+	char params[256];
+	char data[SECTOR_SIZE];
+	int fd;
+
+	snprintf(params, sizeof(params), "aes-xts-plain64 %s 0 %s %d",
+		 CRYPT_KEY, loop_path, CRYPT_OFFSET);
+	TEST_ERRNO(load_table("crypt", 0, "aes-xts-plain64 00 0 /dev/null 0"),
+		   EINVAL);
+	TEST_SUCC(load_table("crypt", 0, params));
+	TEST_SUCC(dm_ioctl(DM_DEV_SUSPEND, DM_NAME, DM_SUSPEND_FLAG));
+	TEST_SUCC(dm_ioctl(DM_DEV_SUSPEND, DM_NAME, 0));
+
+	fd = TEST_SUCC(open(dm_path, O_RDWR));
+	memset(data, 0, sizeof(data));
+	strcpy(data, "secret");
+	TEST_RES(pwrite(fd, data, SECTOR_SIZE, 0), _ret == SECTOR_SIZE);
+
+	memset(data, 0, sizeof(data));
+	TEST_RES(pread(fd, data, SECTOR_SIZE, 0),
+		 _ret == SECTOR_SIZE && strcmp(data, "secret") == 0);
+	TEST_SUCC(close(fd));
+
+	TEST_RES(pread(image_fd, data, SECTOR_SIZE,
+		       CRYPT_OFFSET * SECTOR_SIZE),
+		 _ret == SECTOR_SIZE && memcmp(data, "secret", 6) != 0);
+}
+END_TEST()
+
+FN_TEST(remove)
+{
+	TEST_SUCC(dm_ioctl(DM_DEV_REMOVE, DM_NAME, 0));
+	TEST_ERRNO(access(dm_path, F_OK), ENOENT);
+	TEST_ERRNO(dm_ioctl(DM_DEV_REMOVE, DM_NAME, 0), ENXIO);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(ctl_fd));
+	CHECK(ioctl(loop_fd, LOOP_CLR_FD));
+	CHECK(close(loop_fd));
+	CHECK(close(image_fd));
+	CHECK(unlink(IMAGE_PATH));
+}
+END_SETUP()
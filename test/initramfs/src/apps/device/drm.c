@@ -0,0 +1,245 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+#include <drm/drm.h>
+#include <drm/drm_mode.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <poll.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/ioctl.h>
+#include <sys/mman.h>
+#include <unistd.h>
+
+#include "../common/test.h"
+
+#define DRM_DEVICE "/dev/dri/card0"
+
+static int fd = -1;
+
+static uint32_t crtc_id;
+static uint32_t connector_id;
+static struct drm_mode_modeinfo mode;
+
+FN_SETUP(open_card)
+{
+	fd = open(DRM_DEVICE, O_RDWR | O_NONBLOCK);
+	if (fd < 0 && errno == ENOENT) {
+		fprintf(stderr, "DRM tests skipped: %s does not exist\n",
+			DRM_DEVICE);
+		exit(EXIT_SUCCESS);
+	}
+	CHECK(fd);
+}
+END_SETUP()
+
+FN_TEST(version)
+{
+	char name[32] = {};
+	struct drm_version version = {
+		.name = name,
+		.name_len = 4,
+	};
+
+	// Only the first `name_len` bytes are copied, but the full length is reported.
+	TEST_RES(ioctl(fd, DRM_IOCTL_VERSION, &version),
+		 version.name_len == strlen("virtio_gpu") &&
+			 strcmp(name, "virt") == 0);
+
+	TEST_RES(ioctl(fd, DRM_IOCTL_VERSION, &version),
+		 strcmp(name, "virtio_gpu") == 0);
+}
+END_TEST()
+
+FN_TEST(capabilities)
+{
+	struct drm_get_cap cap = { .capability = DRM_CAP_DUMB_BUFFER };
+	struct drm_set_client_cap client_cap = {
+		.capability = DRM_CLIENT_CAP_UNIVERSAL_PLANES,
+		.value = 2,
+	};
+
+	TEST_RES(ioctl(fd, DRM_IOCTL_GET_CAP, &cap), cap.value == 1);
+
+	cap.capability = 0xdead;
+	TEST_ERRNO(ioctl(fd, DRM_IOCTL_GET_CAP, &cap), EINVAL);
+
+	TEST_ERRNO(ioctl(fd, DRM_IOCTL_SET_CLIENT_CAP, &client_cap), EINVAL);
+	client_cap.value = 1;
+	TEST_SUCC(ioctl(fd, DRM_IOCTL_SET_CLIENT_CAP, &client_cap));
+
+	client_cap.capability = DRM_CLIENT_CAP_ATOMIC;
+	TEST_ERRNO(ioctl(fd, DRM_IOCTL_SET_CLIENT_CAP, &client_cap),
+		   EOPNOTSUPP);
+}
+END_TEST()
+
+FN_TEST(master)
+{
+	int fd2;
+
+	// The first opened file is the master.
+	fd2 = TEST_SUCC(open(DRM_DEVICE, O_RDWR));
+	TEST_ERRNO(ioctl(fd2, DRM_IOCTL_SET_MASTER, 0), EBUSY);
+	TEST_ERRNO(ioctl(fd2, DRM_IOCTL_DROP_MASTER, 0), EINVAL);
+
+	TEST_SUCC(ioctl(fd, DRM_IOCTL_DROP_MASTER, 0));
+	TEST_ERRNO(ioctl(fd, DRM_IOCTL_DROP_MASTER, 0), EINVAL);
+	TEST_SUCC(ioctl(fd2, DRM_IOCTL_SET_MASTER, 0));
+	TEST_ERRNO(ioctl(fd, DRM_IOCTL_SET_MASTER, 0), EBUSY);
+
+	// Closing the master file drops the master.
+	TEST_SUCC(close(fd2));
+	TEST_SUCC(ioctl(fd, DRM_IOCTL_SET_MASTER, 0));
+	TEST_SUCC(ioctl(fd, DRM_IOCTL_SET_MASTER, 0));
+}
+END_TEST()
+
+FN_TEST(resources)
+{
+	struct drm_mode_card_res res = {};
+	struct drm_mode_get_connector conn = {};
+	struct drm_mode_modeinfo modes[16];
+
+	TEST_RES(ioctl(fd, DRM_IOCTL_MODE_GETRESOURCES, &res),
+		 res.count_crtcs >= 1 &&
+			 res.count_connectors == res.count_crtcs &&
+			 res.count_encoders == res.count_crtcs &&
+			 res.count_fbs == 0);
+
+	res.count_crtcs = 1;
+	res.crtc_id_ptr = (uintptr_t)&crtc_id;
+	res.count_connectors = 1;
+	res.connector_id_ptr = (uintptr_t)&connector_id;
+	res.count_encoders = 0;
+	TEST_RES(ioctl(fd, DRM_IOCTL_MODE_GETRESOURCES, &res),
+		 crtc_id != 0 && connector_id != 0);
+
+	conn.connector_id = connector_id;
+	// QEMU enables the first scanout by default.
+	TEST_RES(ioctl(fd, DRM_IOCTL_MODE_GETCONNECTOR, &conn),
+		 conn.connector_type == DRM_MODE_CONNECTOR_VIRTUAL &&
+			 conn.connection == 1 && conn.count_encoders == 1 &&
+			 conn.count_modes >= 1 && conn.count_modes <= 16);
+
+	conn.count_encoders = 0;
+	conn.modes_ptr = (uintptr_t)modes;
+	TEST_RES(ioctl(fd, DRM_IOCTL_MODE_GETCONNECTOR, &conn),
+		 (modes[0].type & DRM_MODE_TYPE_PREFERRED) != 0);
+	mode = modes[0];
+}
+END_TEST()
+
+FN_TEST(dumb_buffer)
+{
+	struct drm_mode_create_dumb create = {
+		.width = 100,
+		.height = 10,
+		.bpp = 24,
+	};
+	struct drm_mode_map_dumb map = {};
+	struct drm_mode_destroy_dumb destroy = {};
+	uint32_t *pixels;
+
+	TEST_ERRNO(ioctl(fd, DRM_IOCTL_MODE_CREATE_DUMB, &create), EINVAL);
+
+	create.bpp = 32;
+	TEST_RES(ioctl(fd, DRM_IOCTL_MODE_CREATE_DUMB, &create),
+		 create.handle != 0 && create.pitch == 400 &&
+			 create.size == 4096);
+
+	map.handle = create.handle;
+	TEST_SUCC(ioctl(fd, DRM_IOCTL_MODE_MAP_DUMB, &map));
+	pixels = CHECK_WITH(mmap(NULL, create.size, PROT_READ | PROT_WRITE,
+				 MAP_SHARED, fd, map.offset),
+			    _ret != MAP_FAILED);
+	TEST_RES(pixels[0], _ret == 0);
+	pixels[0] = 0xffffff;
+	TEST_SUCC(munmap(pixels, create.size));
+
+	destroy.handle = create.handle;
+	TEST_SUCC(ioctl(fd, DRM_IOCTL_MODE_DESTROY_DUMB, &destroy));
+	TEST_ERRNO(ioctl(fd, DRM_IOCTL_MODE_DESTROY_DUMB, &destroy), EINVAL);
+	TEST_ERRNO(ioctl(fd, DRM_IOCTL_MODE_MAP_DUMB, &map), ENOENT);
+}
+END_TEST()
+
+FN_TEST(mode_setting)
+{
+	struct drm_mode_create_dumb create = {
+		.width = mode.hdisplay,
+		.height = mode.vdisplay,
+		.bpp = 32,
+	};
+	struct drm_mode_fb_cmd fb = {};
+	struct drm_mode_crtc crtc = {};
+	struct drm_mode_crtc_page_flip flip = {};
+	struct drm_event_vblank event;
+	struct pollfd pfd = { .fd = fd, .events = POLLIN };
+
+	TEST_SUCC(ioctl(fd, DRM_IOCTL_MODE_CREATE_DUMB, &create));
+
+	fb.width = create.width;
+	fb.height = create.height;
+	fb.pitch = create.pitch;
+	fb.bpp = 32;
+	fb.depth = 24;
+	fb.handle = create.handle;
+	TEST_RES(ioctl(fd, DRM_IOCTL_MODE_ADDFB, &fb), fb.fb_id != 0);
+
+	crtc.crtc_id = crtc_id;
+	crtc.fb_id = fb.fb_id;
+	crtc.set_connectors_ptr = (uintptr_t)&connector_id;
+	crtc.count_connectors = 1;
+	crtc.mode = mode;
+	crtc.mode_valid = 1;
+	crtc.x = 1;
+	TEST_ERRNO(ioctl(fd, DRM_IOCTL_MODE_SETCRTC, &crtc), ENOSPC);
+	crtc.x = 0;
+	TEST_SUCC(ioctl(fd, DRM_IOCTL_MODE_SETCRTC, &crtc));
+
+	memset(&crtc, 0, sizeof(crtc));
+	crtc.crtc_id = crtc_id;
+	TEST_RES(ioctl(fd, DRM_IOCTL_MODE_GETCRTC, &crtc),
+		 crtc.fb_id == fb.fb_id && crtc.mode_valid == 1 &&
+			 crtc.mode.hdisplay == mode.hdisplay);
+
+	// No events are pending.
+	TEST_ERRNO(read(fd, &event, sizeof(event)), EAGAIN);
+
+	flip.crtc_id = crtc_id;
+	flip.fb_id = fb.fb_id;
+	flip.flags = DRM_MODE_PAGE_FLIP_EVENT | DRM_MODE_PAGE_FLIP_ASYNC;
+	TEST_ERRNO(ioctl(fd, DRM_IOCTL_MODE_PAGE_FLIP, &flip), EINVAL);
+	flip.flags = DRM_MODE_PAGE_FLIP_EVENT;
+	flip.user_data = 0x1234;
+	TEST_SUCC(ioctl(fd, DRM_IOCTL_MODE_PAGE_FLIP, &flip));
+
+	TEST_RES(poll(&pfd, 1, 1000), _ret == 1 && pfd.revents == POLLIN);
+	// The buffer is too small for the event.
+	TEST_RES(read(fd, &event, sizeof(event) - 1), _ret == 0);
+	TEST_RES(read(fd, &event, sizeof(event)),
+		 _ret == sizeof(event) &&
+			 event.base.type == DRM_EVENT_FLIP_COMPLETE &&
+			 event.base.length == sizeof(event) &&
+			 event.user_data == 0x1234 && event.crtc_id == crtc_id);
+
+	// Removing the framebuffer disables the CRTC.
+	TEST_SUCC(ioctl(fd, DRM_IOCTL_MODE_RMFB, &fb.fb_id));
+	TEST_ERRNO(ioctl(fd, DRM_IOCTL_MODE_RMFB, &fb.fb_id), ENOENT);
+	TEST_RES(ioctl(fd, DRM_IOCTL_MODE_GETCRTC, &crtc),
+		 crtc.fb_id == 0 && crtc.mode_valid == 0);
+
+	TEST_SUCC(ioctl(fd, DRM_IOCTL_GEM_CLOSE,
+			&(struct drm_gem_close){ .handle = create.handle }));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(fd));
+}
+END_SETUP()
@@ -4,9 +4,11 @@
 #include <errno.h>
 #include <fcntl.h>
 #include <limits.h>
+#include <linux/input.h>
 #include <stdio.h>
 #include <stdlib.h>
 #include <string.h>
+#include <sys/ioctl.h>
 #include <unistd.h>
 
 #include "../common/test.h"
@@ -16,6 +18,7 @@
 
 static int evdev_fds[MAX_EVDEV_DEVICES];
 static size_t evdev_count = 0;
+static char first_evdev_path[PATH_MAX];
 
 static void cleanup_open_fds(DIR *dir)
 {
@@ -76,6 +79,9 @@ FN_SETUP(open_evdev)
 			exit(EXIT_FAILURE);
 		}
 
+		if (evdev_count == 0) {
+			strcpy(first_evdev_path, path);
+		}
 		evdev_fds[evdev_count++] = fd;
 	}
 
@@ -101,6 +107,59 @@ FN_TEST(open_close)
 }
 END_TEST()
 
+FN_TEST(abs_info)
+{
+	for (size_t i = 0; i < evdev_count; ++i) {
+		int fd = evdev_fds[i];
+		unsigned long ev_bits = 0;
+		unsigned char abs_bits[ABS_MAX / 8 + 1] = {};
+		unsigned char prop_bits[INPUT_PROP_MAX / 8 + 1];
+		struct input_absinfo info;
+
+		TEST_SUCC(ioctl(fd, EVIOCGBIT(0, sizeof(ev_bits)), &ev_bits));
+		TEST_SUCC(ioctl(fd, EVIOCGBIT(EV_ABS, sizeof(abs_bits)),
+				abs_bits));
+		TEST_SUCC(ioctl(fd, EVIOCGPROP(sizeof(prop_bits)), prop_bits));
+
+		if (!(ev_bits & (1UL << EV_ABS))) {
+			TEST_ERRNO(ioctl(fd, EVIOCGABS(ABS_X), &info), EINVAL);
+			continue;
+		}
+
+		for (int abs = 0; abs <= ABS_MAX; ++abs) {
+			if (!(abs_bits[abs / 8] & (1 << (abs % 8)))) {
+				continue;
+			}
+			TEST_RES(ioctl(fd, EVIOCGABS(abs), &info),
+				 info.minimum <= info.maximum);
+		}
+	}
+}
+END_TEST()
+
+FN_TEST(grab)
+{
+	int fd = evdev_fds[0];
+	int fd2;
+
+	fd2 = TEST_SUCC(open(first_evdev_path, O_RDONLY));
+
+	TEST_ERRNO(ioctl(fd, EVIOCGRAB, 0), EINVAL);
+	TEST_SUCC(ioctl(fd, EVIOCGRAB, 1));
+	TEST_ERRNO(ioctl(fd, EVIOCGRAB, 1), EBUSY);
+	TEST_ERRNO(ioctl(fd2, EVIOCGRAB, 1), EBUSY);
+	TEST_ERRNO(ioctl(fd2, EVIOCGRAB, 0), EINVAL);
+	TEST_SUCC(ioctl(fd, EVIOCGRAB, 0));
+	TEST_ERRNO(ioctl(fd, EVIOCGRAB, 0), EINVAL);
+
+	// Closing the file releases the grab.
+	TEST_SUCC(ioctl(fd2, EVIOCGRAB, 1));
+	TEST_SUCC(close(fd2));
+	TEST_SUCC(ioctl(fd, EVIOCGRAB, 1));
+	TEST_SUCC(ioctl(fd, EVIOCGRAB, 0));
+}
+END_TEST()
+
 FN_SETUP(close_evdev)
 {
 	for (size_t i = 0; i < evdev_count; ++i) {
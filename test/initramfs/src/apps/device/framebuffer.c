@@ -115,6 +115,25 @@ FN_TEST(color_map)
 }
 END_TEST()
 
+FN_TEST(con2fb_map)
+{
+	struct fb_con2fbmap con2fb = { .console = 1, .framebuffer = 0xff };
+
+	TEST_RES(ioctl(fb_fd, FBIOGET_CON2FBMAP, &con2fb),
+		 _ret == 0 && con2fb.framebuffer == 0);
+	TEST_SUCC(ioctl(fb_fd, FBIOPUT_CON2FBMAP, &con2fb));
+
+	// The framebuffer that does not exist cannot be used.
+	con2fb.framebuffer = FB_MAX - 1;
+	TEST_ERRNO(ioctl(fb_fd, FBIOPUT_CON2FBMAP, &con2fb), EINVAL);
+
+	// Virtual consoles are numbered from 1.
+	con2fb.console = 0;
+	TEST_ERRNO(ioctl(fb_fd, FBIOGET_CON2FBMAP, &con2fb), EINVAL);
+	TEST_ERRNO(ioctl(fb_fd, FBIOPUT_CON2FBMAP, &con2fb), EINVAL);
+}
+END_TEST()
+
 FN_TEST(write_enospc)
 {
 	const unsigned char test_pattern = 0xff;
@@ -0,0 +1,129 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <poll.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/klog.h>
+#include <sys/stat.h>
+#include <sys/sysmacros.h>
+#include <unistd.h>
+
+#include "../common/test.h"
+
+#define SYSLOG_ACTION_READ_ALL 3
+#define SYSLOG_ACTION_CLEAR 5
+#define SYSLOG_ACTION_CONSOLE_LEVEL 8
+#define SYSLOG_ACTION_SIZE_BUFFER 10
+
+#define MESSAGE "kmsg test message"
+#define PRINTK_SYSCTL "/proc/sys/kernel/printk"
+
+static int fd = -1;
+static char buf[4096];
+
+FN_SETUP(open_kmsg)
+{
+	fd = CHECK(open("/dev/kmsg", O_RDWR | O_NONBLOCK));
+}
+END_SETUP()
+
+FN_TEST(fstat)
+{
+	struct stat stat;
+
+	TEST_RES(fstat(fd, &stat),
+		 S_ISCHR(stat.st_mode) && stat.st_rdev == makedev(1, 11));
+}
+END_TEST()
+
+FN_TEST(read_own_message)
+{
+	struct pollfd pfd = { .fd = fd, .events = POLLIN };
+	ssize_t len;
+
+	// Skip the records that already exist.
+	while (read(fd, buf, sizeof(buf)) > 0 || errno == EPIPE)
+		;
+	TEST_RES(poll(&pfd, 1, 0), _ret == 0);
+
+	TEST_RES(write(fd, "<13>" MESSAGE "\n", strlen(MESSAGE) + 5),
+		 _ret == strlen(MESSAGE) + 5);
+	TEST_RES(poll(&pfd, 1, 0), _ret == 1 && pfd.revents == POLLIN);
+
+	// The record is `<priority>,<sequence>,<timestamp>,<flags>;<text>\n`.
+	len = TEST_SUCC(read(fd, buf, sizeof(buf) - 1));
+	buf[len] = '\0';
+	TEST_RES(0, strncmp(buf, "13,", 3) == 0);
+	TEST_RES(0, strstr(buf, ";" MESSAGE "\n") != NULL);
+
+	TEST_ERRNO(read(fd, buf, sizeof(buf)), EAGAIN);
+}
+END_TEST()
+
+FN_TEST(read_small_buffer)
+{
+	TEST_RES(write(fd, MESSAGE, strlen(MESSAGE)), _ret == strlen(MESSAGE));
+	TEST_ERRNO(read(fd, buf, 4), EINVAL);
+	TEST_RES(read(fd, buf, sizeof(buf)), _ret > strlen(MESSAGE));
+}
+END_TEST()
+
+FN_TEST(syslog_read_all)
+{
+	int len;
+
+	TEST_RES(klogctl(SYSLOG_ACTION_SIZE_BUFFER, NULL, 0), _ret > 0);
+
+	TEST_SUCC(klogctl(SYSLOG_ACTION_CLEAR, NULL, 0));
+
+	TEST_RES(write(fd, "<14>" MESSAGE, strlen(MESSAGE) + 4),
+		 _ret == strlen(MESSAGE) + 4);
+	len = TEST_SUCC(klogctl(SYSLOG_ACTION_READ_ALL, buf, sizeof(buf) - 1));
+	buf[len] = '\0';
+	TEST_RES(0, strstr(buf, "<14>[") != NULL &&
+			    strstr(buf, "] " MESSAGE "\n") != NULL);
+}
+END_TEST()
+
+FN_TEST(syslog_invalid)
+{
+	TEST_ERRNO(klogctl(-1, NULL, 0), EINVAL);
+	TEST_ERRNO(klogctl(SYSLOG_ACTION_READ_ALL, NULL, 10), EINVAL);
+	TEST_ERRNO(klogctl(SYSLOG_ACTION_READ_ALL, buf, -1), EINVAL);
+}
+END_TEST()
+
+FN_TEST(printk_sysctl)
+{
+	int printk_fd;
+	ssize_t len;
+
+	printk_fd = TEST_SUCC(open(PRINTK_SYSCTL, O_RDWR));
+
+	TEST_RES(write(printk_fd, "5 4 1 7\n", 8), _ret == 8);
+	len = TEST_SUCC(pread(printk_fd, buf, sizeof(buf) - 1, 0));
+	buf[len] = '\0';
+	TEST_RES(0, strcmp(buf, "5\t4\t1\t7\n") == 0);
+
+	// The console log level set by syslog(2) is visible in the sysctl.
+	TEST_SUCC(klogctl(SYSLOG_ACTION_CONSOLE_LEVEL, NULL, 3));
+	len = TEST_SUCC(pread(printk_fd, buf, sizeof(buf) - 1, 0));
+	buf[len] = '\0';
+	TEST_RES(0, strncmp(buf, "3\t", 2) == 0);
+	TEST_ERRNO(klogctl(SYSLOG_ACTION_CONSOLE_LEVEL, NULL, 9), EINVAL);
+
+	TEST_ERRNO(write(printk_fd, "x\n", 2), EINVAL);
+	TEST_RES(write(printk_fd, "8 4 1 8\n", 8), _ret == 8);
+
+	TEST_SUCC(close(printk_fd));
+}
+END_TEST()
+
+FN_SETUP(close_kmsg)
+{
+	CHECK(close(fd));
+}
+END_SETUP()
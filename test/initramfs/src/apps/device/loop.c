@@ -0,0 +1,196 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+#include <fcntl.h>
+#include <linux/fs.h>
+#include <linux/loop.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/ioctl.h>
+#include <sys/stat.h>
+#include <sys/sysmacros.h>
+#include <unistd.h>
+
+#include "../common/test.h"
+
+#define IMAGE_PATH "/tmp/loop_test.img"
+#define IMAGE_SIZE (1024 * 1024)
+#define SECTOR_SIZE 512
+
+#define PART_START_SECTOR 8
+#define PART_NR_SECTORS 64
+
+static int ctl_fd;
+static int image_fd;
+static int loop_fd;
+static int loop_index;
+static char loop_path[32];
+
+// Writes an MBR containing a single primary partition.
+static void write_mbr(int fd)
+{
+	uint8_t mbr[SECTOR_SIZE] = { 0 };
+	uint8_t *entry = &mbr[446];
+	uint32_t start = PART_START_SECTOR;
+	uint32_t nr_sectors = PART_NR_SECTORS;
+
+	entry[4] = 0x83;
+	memcpy(&entry[8], &start, sizeof(start));
+	memcpy(&entry[12], &nr_sectors, sizeof(nr_sectors));
+	mbr[510] = 0x55;
+	mbr[511] = 0xAA;
+
+	CHECK_WITH(pwrite(fd, mbr, sizeof(mbr), 0), _ret == sizeof(mbr));
+}
+
+FN_SETUP(image)
+{
+	image_fd = CHECK(open(IMAGE_PATH, O_CREAT | O_RDWR | O_TRUNC, 0600));
+	CHECK(ftruncate(image_fd, IMAGE_SIZE));
+	write_mbr(image_fd);
+	CHECK_WITH(pwrite(image_fd, "partition", 9,
+			  PART_START_SECTOR * SECTOR_SIZE),
+		   _ret == 9);
+}
+END_SETUP()
+
+FN_SETUP(control)
+{
+	struct stat stat_buf;
+
+	ctl_fd = CHECK(open("/dev/loop-control", O_RDWR));
+	CHECK_WITH(fstat(ctl_fd, &stat_buf),
+		   S_ISCHR(stat_buf.st_mode) &&
+			   stat_buf.st_rdev == makedev(10, 237));
+
+	loop_index = CHECK(ioctl(ctl_fd, LOOP_CTL_GET_FREE));
+	snprintf(loop_path, sizeof(loop_path), "/dev/loop%d", loop_index);
+	loop_fd = CHECK(open(loop_path, O_RDWR));
+}
+END_SETUP()
+
+FN_TEST(unbound)
+{
+	struct loop_info64 info;
+	uint64_t size;
+
+	TEST_ERRNO(ioctl(loop_fd, LOOP_GET_STATUS64, &info), ENXIO);
+	TEST_ERRNO(ioctl(loop_fd, LOOP_CLR_FD), ENXIO);
+	TEST_RES(ioctl(loop_fd, BLKGETSIZE64, &size), size == 0);
+}
+END_TEST()
+
+FN_TEST(configure)
+{
+	struct loop_config config = { 0 };
+	struct loop_info64 info;
+	uint64_t size;
+
+	config.fd = image_fd;
+	config.info.lo_flags = LO_FLAGS_PARTSCAN;
+	strcpy((char *)config.info.lo_file_name, IMAGE_PATH);
+	TEST_SUCC(ioctl(loop_fd, LOOP_CONFIGURE, &config));
+	TEST_ERRNO(ioctl(loop_fd, LOOP_CONFIGURE, &config), EBUSY);
+	TEST_ERRNO(ioctl(loop_fd, LOOP_SET_FD, image_fd), EBUSY);
+
+	TEST_RES(ioctl(loop_fd, BLKGETSIZE64, &size), size == IMAGE_SIZE);
+	TEST_RES(ioctl(loop_fd, LOOP_GET_STATUS64, &info),
+		 info.lo_number == loop_index &&
+			 info.lo_flags == LO_FLAGS_PARTSCAN &&
+			 strcmp((char *)info.lo_file_name, IMAGE_PATH) == 0);
+}
+END_TEST()
+
+FN_TEST(read_write)
+{
+	char buf[SECTOR_SIZE];
+
+	TEST_RES(pread(loop_fd, buf, SECTOR_SIZE,
+		       PART_START_SECTOR * SECTOR_SIZE),
+		 _ret == SECTOR_SIZE && memcmp(buf, "partition", 9) == 0);
+
+	memset(buf, 'x', sizeof(buf));
+	TEST_RES(pwrite(loop_fd, buf, SECTOR_SIZE, IMAGE_SIZE - SECTOR_SIZE),
+		 _ret == SECTOR_SIZE);
+	memset(buf, 0, sizeof(buf));
+	TEST_RES(pread(image_fd, buf, SECTOR_SIZE, IMAGE_SIZE - SECTOR_SIZE),
+		 _ret == SECTOR_SIZE && buf[0] == 'x' &&
+			 buf[SECTOR_SIZE - 1] == 'x');
+}
+END_TEST()
+
+FN_TEST(partition)
+{
+	char part_path[40];
+	char buf[16];
+	uint64_t size;
+	int part_fd;
+
+	snprintf(part_path, sizeof(part_path), "%sp1", loop_path);
+	part_fd = TEST_SUCC(open(part_path, O_RDONLY));
+	TEST_RES(ioctl(part_fd, BLKGETSIZE64, &size),
+		 size == PART_NR_SECTORS * SECTOR_SIZE);
+	TEST_RES(pread(part_fd, buf, 9, 0),
+		 _ret == 9 && memcmp(buf, "partition", 9) == 0);
+	TEST_SUCC(close(part_fd));
+}
+END_TEST()
+
+FN_TEST(set_status)
+{
+	struct loop_info64 info = { 0 };
+	char buf[16];
+	uint64_t size;
+
+	info.lo_offset = PART_START_SECTOR * SECTOR_SIZE;
+	info.lo_sizelimit = PART_NR_SECTORS * SECTOR_SIZE;
+	TEST_SUCC(ioctl(loop_fd, LOOP_SET_STATUS64, &info));
+	TEST_RES(ioctl(loop_fd, BLKGETSIZE64, &size),
+		 size == PART_NR_SECTORS * SECTOR_SIZE);
+	TEST_RES(pread(loop_fd, buf, 9, 0),
+		 _ret == 9 && memcmp(buf, "partition", 9) == 0);
+
+	TEST_ERRNO(ioctl(loop_fd, LOOP_SET_BLOCK_SIZE, 513), EINVAL);
+	TEST_SUCC(ioctl(loop_fd, LOOP_SET_BLOCK_SIZE, 4096));
+	TEST_SUCC(ioctl(loop_fd, LOOP_SET_DIRECT_IO, 1));
+	TEST_RES(ioctl(loop_fd, LOOP_GET_STATUS64, &info),
+		 info.lo_flags & LO_FLAGS_DIRECT_IO);
+	TEST_RES(pread(loop_fd, buf, 9, 0),
+		 _ret == 9 && memcmp(buf, "partition", 9) == 0);
+	TEST_SUCC(ioctl(loop_fd, LOOP_SET_DIRECT_IO, 0));
+}
+END_TEST()
+
+FN_TEST(clear)
+{
+	char part_path[40];
+	uint64_t size;
+
+	TEST_ERRNO(ioctl(ctl_fd, LOOP_CTL_REMOVE, loop_index), EBUSY);
+	TEST_SUCC(ioctl(loop_fd, LOOP_CLR_FD));
+	TEST_RES(ioctl(loop_fd, BLKGETSIZE64, &size), size == 0);
+	snprintf(part_path, sizeof(part_path), "%sp1", loop_path);
+	TEST_ERRNO(access(part_path, F_OK), ENOENT);
+}
+END_TEST()
+
+FN_TEST(add_remove)
+{
+	TEST_ERRNO(ioctl(ctl_fd, LOOP_CTL_ADD, loop_index), EEXIST);
+	TEST_RES(ioctl(ctl_fd, LOOP_CTL_ADD, 1000), _ret == 1000);
+	TEST_SUCC(access("/dev/loop1000", F_OK));
+	TEST_SUCC(ioctl(ctl_fd, LOOP_CTL_REMOVE, 1000));
+	TEST_ERRNO(access("/dev/loop1000", F_OK), ENOENT);
+	TEST_ERRNO(ioctl(ctl_fd, LOOP_CTL_REMOVE, 1000), ENODEV);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(loop_fd));
+	CHECK(close(ctl_fd));
+	CHECK(close(image_fd));
+	CHECK(unlink(IMAGE_PATH));
+}
+END_SETUP()
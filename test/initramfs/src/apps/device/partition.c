@@ -0,0 +1,269 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+#include <fcntl.h>
+#include <linux/fs.h>
+#include <linux/loop.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/ioctl.h>
+#include <unistd.h>
+
+#include "../common/test.h"
+
+#define IMAGE_PATH "/tmp/partition_test.img"
+#define SECTOR_SIZE 512
+#define IMAGE_SECTORS 2048
+#define IMAGE_SIZE (IMAGE_SECTORS * SECTOR_SIZE)
+
+#define GPT_NR_ENTRIES 128
+#define GPT_ENTRY_SIZE 128
+#define GPT_ENTRIES_SECTORS (GPT_NR_ENTRIES * GPT_ENTRY_SIZE / SECTOR_SIZE)
+
+static int image_fd;
+static int loop_fd;
+static char loop_path[32];
+static char loop_name[16];
+
+static uint32_t crc32(const uint8_t *data, size_t len)
+{
+	uint32_t crc = ~0u;
+
+	for (size_t i = 0; i < len; i++) {
+		crc ^= data[i];
+		for (int j = 0; j < 8; j++)
+			crc = (crc & 1) ? (crc >> 1) ^ 0xEDB88320 : crc >> 1;
+	}
+
+	return ~crc;
+}
+
+static void write_mbr_entry(uint8_t *mbr, int index, uint8_t type,
+			    uint32_t start, uint32_t nr_sectors)
+{
+	uint8_t *entry = &mbr[446 + index * 16];
+
+	entry[2] = 1; // The sector of the start CHS address
+	entry[4] = type;
+	entry[6] = 1; // The sector of the end CHS address
+	memcpy(&entry[8], &start, sizeof(start));
+	memcpy(&entry[12], &nr_sectors, sizeof(nr_sectors));
+}
+
+// Writes an MBR with two partitions. The second one extends beyond the end of the disk.
+static void write_mbr(void)
+{
+	uint8_t mbr[SECTOR_SIZE] = { 0 };
+
+	write_mbr_entry(mbr, 0, 0x83, 64, 128);
+	write_mbr_entry(mbr, 1, 0x83, 1024, 4096);
+	mbr[510] = 0x55;
+	mbr[511] = 0xAA;
+
+	CHECK_WITH(pwrite(image_fd, mbr, sizeof(mbr), 0), _ret == sizeof(mbr));
+}
+
+// Writes a GPT with a single partition, whose entry is the third one.
+static void write_gpt(void)
+{
+	uint8_t mbr[SECTOR_SIZE] = { 0 };
+	uint8_t header[SECTOR_SIZE] = { 0 };
+	uint8_t entries[GPT_NR_ENTRIES * GPT_ENTRY_SIZE] = { 0 };
+	uint8_t *entry = &entries[2 * GPT_ENTRY_SIZE];
+	uint64_t u64;
+	uint32_t u32;
+
+	write_mbr_entry(mbr, 0, 0xEE, 1, IMAGE_SECTORS - 1);
+	mbr[510] = 0x55;
+	mbr[511] = 0xAA;
+	CHECK_WITH(pwrite(image_fd, mbr, sizeof(mbr), 0), _ret == sizeof(mbr));
+
+	memset(entry, 0xAB, 16); // The partition type GUID
+	u64 = 256;
+	memcpy(&entry[32], &u64, sizeof(u64));
+	u64 = 511;
+	memcpy(&entry[40], &u64, sizeof(u64));
+	CHECK_WITH(pwrite(image_fd, entries, sizeof(entries), 2 * SECTOR_SIZE),
+		   _ret == sizeof(entries));
+
+	memcpy(header, "EFI PART", 8);
+	u32 = 0x00010000;
+	memcpy(&header[8], &u32, sizeof(u32));
+	u32 = 92;
+	memcpy(&header[12], &u32, sizeof(u32));
+	u64 = 1;
+	memcpy(&header[24], &u64, sizeof(u64));
+	u64 = IMAGE_SECTORS - 1;
+	memcpy(&header[32], &u64, sizeof(u64));
+	u64 = 2 + GPT_ENTRIES_SECTORS;
+	memcpy(&header[40], &u64, sizeof(u64));
+	u64 = IMAGE_SECTORS - 2 - GPT_ENTRIES_SECTORS;
+	memcpy(&header[48], &u64, sizeof(u64));
+	u64 = 2;
+	memcpy(&header[72], &u64, sizeof(u64));
+	u32 = GPT_NR_ENTRIES;
+	memcpy(&header[80], &u32, sizeof(u32));
+	u32 = GPT_ENTRY_SIZE;
+	memcpy(&header[84], &u32, sizeof(u32));
+	u32 = crc32(entries, sizeof(entries));
+	memcpy(&header[88], &u32, sizeof(u32));
+	u32 = crc32(header, 92);
+	memcpy(&header[16], &u32, sizeof(u32));
+	CHECK_WITH(pwrite(image_fd, header, sizeof(header), SECTOR_SIZE),
+		   _ret == sizeof(header));
+}
+
+static uint64_t partition_size(int number)
+{
+	char path[40];
+	uint64_t size;
+	int fd;
+
+	snprintf(path, sizeof(path), "%sp%d", loop_path, number);
+	fd = open(path, O_RDONLY);
+	if (fd < 0)
+		return 0;
+	if (ioctl(fd, BLKGETSIZE64, &size) < 0)
+		size = 0;
+	close(fd);
+
+	return size;
+}
+
+static int read_sysfs_u64(const char *name, const char *attr, uint64_t *val)
+{
+	char path[64];
+	char buf[32];
+	ssize_t len;
+	int fd;
+
+	snprintf(path, sizeof(path), "/sys/class/block/%s/%s", name, attr);
+	fd = open(path, O_RDONLY);
+	if (fd < 0)
+		return -1;
+	len = read(fd, buf, sizeof(buf) - 1);
+	close(fd);
+	if (len <= 0)
+		return -1;
+	buf[len] = '\0';
+	*val = strtoull(buf, NULL, 10);
+
+	return 0;
+}
+
+static int proc_partitions_contains(const char *name)
+{
+	char buf[4096];
+	char pattern[32];
+	ssize_t len;
+	int fd;
+
+	fd = open("/proc/partitions", O_RDONLY);
+	if (fd < 0)
+		return -1;
+	len = read(fd, buf, sizeof(buf) - 1);
+	close(fd);
+	if (len < 0)
+		return -1;
+	buf[len] = '\0';
+
+	snprintf(pattern, sizeof(pattern), " %s\n", name);
+	return strstr(buf, pattern) != NULL;
+}
+
+FN_SETUP(loop)
+{
+	struct loop_config config = { 0 };
+	int ctl_fd;
+	int index;
+
+	image_fd = CHECK(open(IMAGE_PATH, O_CREAT | O_RDWR | O_TRUNC, 0600));
+	CHECK(ftruncate(image_fd, IMAGE_SIZE));
+	write_mbr();
+
+	ctl_fd = CHECK(open("/dev/loop-control", O_RDWR));
+	index = CHECK(ioctl(ctl_fd, LOOP_CTL_GET_FREE));
+	CHECK(close(ctl_fd));
+
+	snprintf(loop_name, sizeof(loop_name), "loop%d", index);
+	snprintf(loop_path, sizeof(loop_path), "/dev/%s", loop_name);
+	loop_fd = CHECK(open(loop_path, O_RDWR));
+	config.fd = image_fd;
+	CHECK(ioctl(loop_fd, LOOP_CONFIGURE, &config));
+}
+END_SETUP()
+
+FN_TEST(no_partscan)
+{
+	TEST_ERRNO(ioctl(loop_fd, BLKRRPART), EINVAL);
+	TEST_RES(partition_size(1), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(mbr)
+{
+	struct loop_info64 info;
+	char name[24];
+	uint64_t val;
+
+	TEST_SUCC(ioctl(loop_fd, LOOP_GET_STATUS64, &info));
+	info.lo_flags |= LO_FLAGS_PARTSCAN;
+	TEST_SUCC(ioctl(loop_fd, LOOP_SET_STATUS64, &info));
+
+	TEST_RES(partition_size(1), _ret == 128 * SECTOR_SIZE);
+	// The second partition is truncated at the end of the disk.
+	TEST_RES(partition_size(2), _ret == (IMAGE_SECTORS - 1024) * SECTOR_SIZE);
+
+	snprintf(name, sizeof(name), "%sp2", loop_name);
+	TEST_RES(read_sysfs_u64(name, "start", &val), val == 1024);
+	TEST_RES(read_sysfs_u64(name, "partition", &val), val == 2);
+	TEST_RES(proc_partitions_contains(name), _ret == 1);
+}
+END_TEST()
+
+FN_TEST(rescan_gpt)
+{
+	char path[40];
+	char name[24];
+	int part_fd;
+
+	write_gpt();
+	TEST_SUCC(ioctl(loop_fd, BLKRRPART));
+
+	TEST_RES(partition_size(1), _ret == 0);
+	TEST_RES(partition_size(2), _ret == 0);
+	TEST_RES(partition_size(3), _ret == 256 * SECTOR_SIZE);
+
+	snprintf(name, sizeof(name), "%sp2", loop_name);
+	TEST_RES(proc_partitions_contains(name), _ret == 0);
+	snprintf(name, sizeof(name), "%sp3", loop_name);
+	TEST_RES(proc_partitions_contains(name), _ret == 1);
+
+	snprintf(path, sizeof(path), "%sp3", loop_path);
+	part_fd = TEST_SUCC(open(path, O_RDONLY));
+	TEST_ERRNO(ioctl(part_fd, BLKRRPART), EINVAL);
+	TEST_SUCC(close(part_fd));
+}
+END_TEST()
+
+FN_TEST(bad_gpt_crc)
+{
+	uint8_t byte = 0xFF;
+
+	// Corrupt the partition entries so that the checksum mismatches.
+	TEST_RES(pwrite(image_fd, &byte, 1, 2 * SECTOR_SIZE), _ret == 1);
+	TEST_SUCC(ioctl(loop_fd, BLKRRPART));
+	TEST_RES(partition_size(3), _ret == 0);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(ioctl(loop_fd, LOOP_CLR_FD));
+	CHECK(close(loop_fd));
+	CHECK(close(image_fd));
+	CHECK(unlink(IMAGE_PATH));
+}
+END_SETUP()
@@ -0,0 +1,345 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+#include <fcntl.h>
+#include <poll.h>
+#include <pty.h>
+#include <signal.h>
+#include <sys/ioctl.h>
+#include <sys/wait.h>
+#include <termios.h>
+#include <unistd.h>
+
+#include "../../common/test.h"
+
+static int master, slave;
+
+static volatile sig_atomic_t received_signal;
+
+static void record_signal(int signum)
+{
+	received_signal = signum;
+}
+
+// The input written to the master may be processed asynchronously (e.g., in Linux), so the signals
+// may not arrive immediately.
+static int wait_for_signal(void)
+{
+	int i;
+
+	for (i = 0; i < 1000 && received_signal == 0; i++)
+		usleep(1000);
+
+	errno = 0;
+	return received_signal;
+}
+
+FN_SETUP(run_in_new_session)
+{
+	int status;
+
+	if (CHECK(fork()) != 0) {
+		CHECK_WITH(wait(&status),
+			   WIFEXITED(status) && WEXITSTATUS(status) == 0);
+		exit(EXIT_SUCCESS);
+	}
+
+	CHECK(setsid());
+}
+END_SETUP()
+
+FN_SETUP(open_pty)
+{
+	CHECK(openpty(&master, &slave, NULL, NULL, NULL));
+	CHECK(ioctl(slave, TIOCSCTTY, 0));
+
+	signal(SIGWINCH, record_signal);
+	signal(SIGTSTP, record_signal);
+	signal(SIGINT, record_signal);
+	signal(SIGHUP, record_signal);
+}
+END_SETUP()
+
+FN_TEST(sigwinch)
+{
+	struct winsize ws = { .ws_row = 24, .ws_col = 80 };
+
+	received_signal = 0;
+	TEST_SUCC(ioctl(master, TIOCSWINSZ, &ws));
+	TEST_RES(received_signal, _ret == SIGWINCH);
+
+	// Setting the same window size does not send `SIGWINCH`.
+	received_signal = 0;
+	TEST_SUCC(ioctl(slave, TIOCSWINSZ, &ws));
+	TEST_RES(received_signal, _ret == 0);
+
+	ws.ws_col = 100;
+	TEST_SUCC(ioctl(slave, TIOCSWINSZ, &ws));
+	TEST_RES(received_signal, _ret == SIGWINCH);
+	TEST_RES(ioctl(master, TIOCGWINSZ, &ws),
+		 ws.ws_row == 24 && ws.ws_col == 100);
+}
+END_TEST()
+
+FN_TEST(isig_in_raw_mode)
+{
+	struct termios term, old_term;
+	char buf[8];
+
+	TEST_SUCC(tcgetattr(slave, &old_term));
+	term = old_term;
+	term.c_lflag &= ~(ICANON | ECHO);
+	term.c_cc[VMIN] = 0;
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &term));
+
+	received_signal = 0;
+	TEST_RES(write(master, "a\032", 2), _ret == 2);
+	TEST_RES(wait_for_signal(), _ret == SIGTSTP);
+	// The signal character is not queued, and the input is flushed.
+	TEST_RES(read(slave, buf, sizeof(buf)), _ret == 0);
+
+	received_signal = 0;
+	TEST_RES(write(master, "\003b", 2), _ret == 2);
+	TEST_RES(wait_for_signal(), _ret == SIGINT);
+	TEST_RES(read(slave, buf, sizeof(buf)), _ret == 1 && buf[0] == 'b');
+
+	// `NOFLSH` prevents the input from being flushed.
+	term.c_lflag |= NOFLSH;
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &term));
+	received_signal = 0;
+	TEST_RES(write(master, "c\003", 2), _ret == 2);
+	TEST_RES(wait_for_signal(), _ret == SIGINT);
+	TEST_RES(read(slave, buf, sizeof(buf)), _ret == 1 && buf[0] == 'c');
+
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &old_term));
+}
+END_TEST()
+
+FN_TEST(ixon)
+{
+	struct termios term, old_term;
+	int packet_mode;
+	char buf[8];
+
+	TEST_SUCC(tcgetattr(slave, &old_term));
+	term = old_term;
+	term.c_iflag |= IXON;
+	term.c_lflag &= ~ECHO;
+	term.c_oflag &= ~OPOST;
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &term));
+
+	packet_mode = 1;
+	TEST_SUCC(ioctl(master, TIOCPKT, &packet_mode));
+
+	// `VSTOP` stops the output.
+	TEST_RES(write(master, "\023", 1), _ret == 1);
+	TEST_RES(read(master, buf, sizeof(buf)),
+		 _ret == 1 && buf[0] == TIOCPKT_STOP);
+
+	TEST_SUCC(fcntl(slave, F_SETFL, O_NONBLOCK));
+	TEST_ERRNO(write(slave, "x", 1), EAGAIN);
+
+	// `VSTART` restarts the output.
+	TEST_RES(write(master, "\021", 1), _ret == 1);
+	TEST_RES(read(master, buf, sizeof(buf)),
+		 _ret == 1 && buf[0] == TIOCPKT_START);
+
+	TEST_RES(write(slave, "x", 1), _ret == 1);
+	TEST_RES(read(master, buf, sizeof(buf)),
+		 _ret == 2 && buf[0] == TIOCPKT_DATA && buf[1] == 'x');
+
+	// The flow control characters are not queued.
+	TEST_ERRNO(read(slave, buf, sizeof(buf)), EAGAIN);
+
+	TEST_SUCC(fcntl(slave, F_SETFL, 0));
+	packet_mode = 0;
+	TEST_SUCC(ioctl(master, TIOCPKT, &packet_mode));
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &old_term));
+}
+END_TEST()
+
+FN_TEST(tcflow)
+{
+	struct termios term, old_term;
+	char buf[8];
+
+	TEST_SUCC(tcgetattr(slave, &old_term));
+	term = old_term;
+	term.c_iflag |= IXON;
+	term.c_oflag &= ~OPOST;
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &term));
+
+	TEST_SUCC(fcntl(slave, F_SETFL, O_NONBLOCK));
+
+	TEST_SUCC(tcflow(slave, TCOOFF));
+	TEST_ERRNO(write(slave, "x", 1), EAGAIN);
+
+	// Only `TCOON` can restart the output stopped by `TCOOFF`.
+	TEST_RES(write(master, "\021", 1), _ret == 1);
+	TEST_ERRNO(write(slave, "x", 1), EAGAIN);
+
+	TEST_SUCC(tcflow(slave, TCOON));
+	TEST_RES(write(slave, "x", 1), _ret == 1);
+	TEST_RES(read(master, buf, sizeof(buf)), _ret == 1 && buf[0] == 'x');
+
+	// `TCIOFF` and `TCION` send the flow control characters.
+	TEST_SUCC(tcflow(slave, TCIOFF));
+	TEST_SUCC(tcflow(slave, TCION));
+	TEST_RES(read(master, buf, sizeof(buf)),
+		 _ret == 2 && buf[0] == '\023' && buf[1] == '\021');
+
+	TEST_ERRNO(tcflow(slave, 4), EINVAL);
+
+	TEST_SUCC(fcntl(slave, F_SETFL, 0));
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &old_term));
+}
+END_TEST()
+
+FN_TEST(tcflush)
+{
+	struct pollfd pfd = { .fd = slave, .events = POLLIN };
+	struct termios term, old_term;
+	int packet_mode;
+	char buf[8];
+
+	TEST_SUCC(tcgetattr(slave, &old_term));
+	term = old_term;
+	term.c_lflag &= ~(ICANON | ECHO);
+	term.c_cc[VMIN] = 0;
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &term));
+
+	packet_mode = 1;
+	TEST_SUCC(ioctl(master, TIOCPKT, &packet_mode));
+
+	TEST_RES(write(master, "abc", 3), _ret == 3);
+	TEST_RES(poll(&pfd, 1, 1000), _ret == 1 && (pfd.revents & POLLIN));
+	TEST_SUCC(tcflush(slave, TCIFLUSH));
+	TEST_RES(read(slave, buf, sizeof(buf)), _ret == 0);
+	TEST_RES(read(master, buf, sizeof(buf)),
+		 _ret == 1 && buf[0] == TIOCPKT_FLUSHREAD);
+
+	TEST_SUCC(tcflush(slave, TCOFLUSH));
+	TEST_RES(read(master, buf, sizeof(buf)),
+		 _ret == 1 && buf[0] == TIOCPKT_FLUSHWRITE);
+
+	TEST_ERRNO(tcflush(slave, 3), EINVAL);
+
+	// `tcdrain()` succeeds immediately.
+	TEST_SUCC(tcdrain(slave));
+
+	packet_mode = 0;
+	TEST_SUCC(ioctl(master, TIOCPKT, &packet_mode));
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &old_term));
+}
+END_TEST()
+
+// Runs `func` in a background process group and returns its wait status.
+static int run_in_background(void (*func)(void))
+{
+	int status;
+	pid_t pid;
+
+	pid = fork();
+	if (pid < 0)
+		return -1;
+
+	if (pid == 0) {
+		signal(SIGTTIN, SIG_DFL);
+		signal(SIGTTOU, SIG_DFL);
+		if (setpgid(0, 0) < 0)
+			_exit(EXIT_FAILURE);
+		func();
+		_exit(EXIT_SUCCESS);
+	}
+
+	if (waitpid(pid, &status, WUNTRACED) != pid)
+		return -1;
+	if (WIFSTOPPED(status)) {
+		kill(pid, SIGKILL);
+		waitpid(pid, NULL, 0);
+	}
+
+	return status;
+}
+
+static void background_read(void)
+{
+	char buf[1];
+
+	read(slave, buf, sizeof(buf));
+}
+
+static void background_read_ignored(void)
+{
+	char buf[1];
+
+	signal(SIGTTIN, SIG_IGN);
+	if (read(slave, buf, sizeof(buf)) < 0 && errno == EIO)
+		_exit(EXIT_SUCCESS);
+	_exit(EXIT_FAILURE);
+}
+
+static void background_write(void)
+{
+	write(slave, "x", 1);
+}
+
+static void background_tcsetattr(void)
+{
+	struct termios term;
+
+	tcgetattr(slave, &term);
+	tcsetattr(slave, TCSANOW, &term);
+}
+
+static void background_tcsetattr_ignored(void)
+{
+	struct termios term;
+
+	signal(SIGTTOU, SIG_IGN);
+	tcgetattr(slave, &term);
+	if (tcsetattr(slave, TCSANOW, &term) < 0)
+		_exit(EXIT_FAILURE);
+}
+
+FN_TEST(background_access)
+{
+	struct termios term, old_term;
+	char buf[8];
+
+	TEST_RES(run_in_background(background_read),
+		 WIFSTOPPED(_ret) && WSTOPSIG(_ret) == SIGTTIN);
+	TEST_RES(run_in_background(background_read_ignored),
+		 WIFEXITED(_ret) && WEXITSTATUS(_ret) == 0);
+
+	// Writes are allowed unless `TOSTOP` is set.
+	TEST_RES(run_in_background(background_write),
+		 WIFEXITED(_ret) && WEXITSTATUS(_ret) == 0);
+	TEST_RES(read(master, buf, sizeof(buf)), _ret == 1 && buf[0] == 'x');
+
+	TEST_SUCC(tcgetattr(slave, &old_term));
+	term = old_term;
+	term.c_lflag |= TOSTOP;
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &term));
+	TEST_RES(run_in_background(background_write),
+		 WIFSTOPPED(_ret) && WSTOPSIG(_ret) == SIGTTOU);
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &old_term));
+
+	TEST_RES(run_in_background(background_tcsetattr),
+		 WIFSTOPPED(_ret) && WSTOPSIG(_ret) == SIGTTOU);
+	TEST_RES(run_in_background(background_tcsetattr_ignored),
+		 WIFEXITED(_ret) && WEXITSTATUS(_ret) == 0);
+}
+END_TEST()
+
+FN_TEST(hang_up)
+{
+	// Closing the master hangs up the slave, which is our controlling
+	// terminal.
+	received_signal = 0;
+	TEST_SUCC(close(master));
+	TEST_RES(received_signal, _ret == SIGHUP);
+
+	TEST_SUCC(close(slave));
+}
+END_TEST()
@@ -0,0 +1,336 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+#include <fcntl.h>
+#include <poll.h>
+#include <pty.h>
+#include <string.h>
+#include <sys/ioctl.h>
+#include <termios.h>
+#include <time.h>
+#include <unistd.h>
+
+#include "../../common/test.h"
+
+static int master, slave;
+static struct termios default_term;
+
+static int64_t now_ms(void)
+{
+	struct timespec ts;
+
+	clock_gettime(CLOCK_MONOTONIC, &ts);
+	return ts.tv_sec * 1000LL + ts.tv_nsec / 1000000;
+}
+
+// Reads the echoed characters from the master, waiting for them to arrive.
+static ssize_t read_echo(char *buf, size_t len)
+{
+	struct pollfd pfd = { .fd = master, .events = POLLIN };
+	ssize_t ret;
+
+	if (poll(&pfd, 1, 1000) != 1)
+		return -1;
+	// The input written to the master may be processed asynchronously (e.g., in Linux), so wait a
+	// bit longer to collect all the echoed characters.
+	usleep(10000);
+
+	ret = read(master, buf, len - 1);
+	if (ret >= 0)
+		buf[ret] = '\0';
+	return ret;
+}
+
+// Waits until the slave has the input to read.
+static int wait_input(void)
+{
+	struct pollfd pfd = { .fd = slave, .events = POLLIN };
+
+	return poll(&pfd, 1, 1000) == 1 ? 0 : -1;
+}
+
+FN_SETUP(open_pty)
+{
+	CHECK(openpty(&master, &slave, NULL, NULL, NULL));
+	CHECK(tcgetattr(slave, &default_term));
+}
+END_SETUP()
+
+FN_TEST(ldisc_number)
+{
+	int ldisc;
+
+	TEST_RES(ioctl(slave, TIOCGETD, &ldisc), ldisc == N_TTY);
+
+	ldisc = N_TTY;
+	TEST_SUCC(ioctl(slave, TIOCSETD, &ldisc));
+	ldisc = -1;
+	TEST_ERRNO(ioctl(slave, TIOCSETD, &ldisc), EINVAL);
+	ldisc = 1000;
+	TEST_ERRNO(ioctl(slave, TIOCSETD, &ldisc), EINVAL);
+
+	TEST_RES(ioctl(slave, TIOCGETD, &ldisc), ldisc == N_TTY);
+}
+END_TEST()
+
+FN_TEST(canon_erase)
+{
+	char buf[64];
+
+	TEST_RES(write(master, "abc\177d\n", 6), _ret == 6);
+	TEST_RES(read_echo(buf, sizeof(buf)),
+		 strcmp(buf, "abc\b \bd\r\n") == 0);
+	TEST_RES(read(slave, buf, sizeof(buf)),
+		 _ret == 4 && memcmp(buf, "abd\n", 4) == 0);
+
+	// `VWERASE` erases the last word and the spaces after it.
+	TEST_RES(write(master, "foo bar  \027baz\n", 14), _ret == 14);
+	TEST_RES(read_echo(buf, sizeof(buf)),
+		 strcmp(buf, "foo bar  \b \b\b \b\b \b\b \b\b \bbaz\r\n") ==
+			 0);
+	TEST_RES(read(slave, buf, sizeof(buf)),
+		 _ret == 8 && memcmp(buf, "foo baz\n", 8) == 0);
+
+	// `VKILL` erases the whole line.
+	TEST_RES(write(master, "xy\025z\n", 5), _ret == 5);
+	TEST_RES(read_echo(buf, sizeof(buf)),
+		 strcmp(buf, "xy\b \b\b \bz\r\n") == 0);
+	TEST_RES(read(slave, buf, sizeof(buf)),
+		 _ret == 2 && memcmp(buf, "z\n", 2) == 0);
+}
+END_TEST()
+
+FN_TEST(canon_erase_ctrl_and_tab)
+{
+	char buf[64];
+
+	// The control characters are echoed as `^X`, so two columns are erased.
+	TEST_RES(write(master, "a\001\177\n", 4), _ret == 4);
+	TEST_RES(read_echo(buf, sizeof(buf)),
+		 strcmp(buf, "a^A\b \b\b \b\r\n") == 0);
+	TEST_RES(read(slave, buf, sizeof(buf)),
+		 _ret == 2 && memcmp(buf, "a\n", 2) == 0);
+
+	// A tab is erased by moving the cursor back to the previous position.
+	TEST_RES(write(master, "ab\t\177\n", 5), _ret == 5);
+	TEST_RES(read_echo(buf, sizeof(buf)),
+		 strcmp(buf, "ab\t\b\b\b\b\b\b\r\n") == 0);
+	TEST_RES(read(slave, buf, sizeof(buf)),
+		 _ret == 3 && memcmp(buf, "ab\n", 3) == 0);
+}
+END_TEST()
+
+FN_TEST(canon_echo_variants)
+{
+	struct termios term = default_term;
+	char buf[64];
+
+	// Without `ECHOE`, the erase character itself is echoed.
+	term.c_lflag &= ~ECHOE;
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &term));
+	TEST_RES(write(master, "ab\177\n", 4), _ret == 4);
+	TEST_RES(read_echo(buf, sizeof(buf)), strcmp(buf, "ab^?\r\n") == 0);
+	TEST_RES(read(slave, buf, sizeof(buf)),
+		 _ret == 2 && memcmp(buf, "a\n", 2) == 0);
+
+	// Without `ECHOKE`, the kill character is echoed with a newline (`ECHOK`).
+	term.c_lflag &= ~ECHOKE;
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &term));
+	TEST_RES(write(master, "ab\025c\n", 5), _ret == 5);
+	TEST_RES(read_echo(buf, sizeof(buf)),
+		 strcmp(buf, "ab^U\r\nc\r\n") == 0);
+	TEST_RES(read(slave, buf, sizeof(buf)),
+		 _ret == 2 && memcmp(buf, "c\n", 2) == 0);
+
+	// With `ECHOPRT`, the erased characters are printed between `\` and `/`.
+	term = default_term;
+	term.c_lflag |= ECHOPRT;
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &term));
+	TEST_RES(write(master, "abc\177\177d\n", 7), _ret == 7);
+	TEST_RES(read_echo(buf, sizeof(buf)),
+		 strcmp(buf, "abc\\cb/d\r\n") == 0);
+	TEST_RES(read(slave, buf, sizeof(buf)),
+		 _ret == 3 && memcmp(buf, "ad\n", 3) == 0);
+
+	// With `ECHONL`, the newline is echoed even if `ECHO` is not set.
+	term = default_term;
+	term.c_lflag &= ~ECHO;
+	term.c_lflag |= ECHONL;
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &term));
+	TEST_RES(write(master, "ab\n", 3), _ret == 3);
+	TEST_RES(read_echo(buf, sizeof(buf)), strcmp(buf, "\r\n") == 0);
+	TEST_RES(read(slave, buf, sizeof(buf)),
+		 _ret == 3 && memcmp(buf, "ab\n", 3) == 0);
+
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &default_term));
+}
+END_TEST()
+
+FN_TEST(canon_lnext_and_reprint)
+{
+	char buf[64];
+
+	// `VLNEXT` makes the next character literal.
+	TEST_RES(write(master, "a\026\177\026\003\n", 6), _ret == 6);
+	TEST_RES(read_echo(buf, sizeof(buf)),
+		 strcmp(buf, "a^\b^?^\b^C\r\n") == 0);
+	TEST_RES(read(slave, buf, sizeof(buf)),
+		 _ret == 4 && memcmp(buf, "a\177\003\n", 4) == 0);
+
+	// `VREPRINT` prints the current line again.
+	TEST_RES(write(master, "ab\022c\n", 5), _ret == 5);
+	TEST_RES(read_echo(buf, sizeof(buf)),
+		 strcmp(buf, "ab^R\r\nabc\r\n") == 0);
+	TEST_RES(read(slave, buf, sizeof(buf)),
+		 _ret == 4 && memcmp(buf, "abc\n", 4) == 0);
+}
+END_TEST()
+
+FN_TEST(canon_eof)
+{
+	struct termios term = default_term;
+	char buf[64];
+	int len;
+
+	term.c_lflag &= ~ECHO;
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &term));
+
+	// `VEOF` completes the line without being read.
+	TEST_RES(write(master, "ab\004\004cd\n", 7), _ret == 7);
+	TEST_SUCC(wait_input());
+	TEST_RES(ioctl(slave, FIONREAD, &len), len == 5);
+	TEST_RES(read(slave, buf, sizeof(buf)),
+		 _ret == 2 && memcmp(buf, "ab", 2) == 0);
+	TEST_RES(read(slave, buf, sizeof(buf)), _ret == 0);
+	TEST_RES(read(slave, buf, sizeof(buf)),
+		 _ret == 3 && memcmp(buf, "cd\n", 3) == 0);
+
+	// A line can be read in multiple parts.
+	TEST_RES(write(master, "abcd\n", 5), _ret == 5);
+	TEST_SUCC(wait_input());
+	TEST_RES(read(slave, buf, 3), _ret == 3 && memcmp(buf, "abc", 3) == 0);
+	TEST_RES(read(slave, buf, 3), _ret == 2 && memcmp(buf, "d\n", 2) == 0);
+
+	// `VEOL` is another line terminator.
+	term.c_cc[VEOL] = '!';
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &term));
+	TEST_RES(write(master, "ab!", 3), _ret == 3);
+	TEST_SUCC(wait_input());
+	TEST_RES(read(slave, buf, sizeof(buf)),
+		 _ret == 3 && memcmp(buf, "ab!", 3) == 0);
+
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &default_term));
+}
+END_TEST()
+
+FN_TEST(input_translation)
+{
+	struct termios term = default_term;
+	char buf[64];
+
+	term.c_lflag &= ~(ICANON | ECHO);
+	term.c_iflag &= ~ICRNL;
+	term.c_iflag |= IGNCR | INLCR;
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &term));
+
+	TEST_RES(write(master, "a\rb\n", 4), _ret == 4);
+	TEST_SUCC(wait_input());
+	usleep(10000);
+	TEST_RES(read(slave, buf, sizeof(buf)),
+		 _ret == 3 && memcmp(buf, "ab\r", 3) == 0);
+
+	term.c_iflag &= ~(IGNCR | INLCR);
+	term.c_iflag |= ISTRIP;
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &term));
+
+	TEST_RES(write(master, "\341", 1), _ret == 1);
+	TEST_SUCC(wait_input());
+	TEST_RES(read(slave, buf, sizeof(buf)), _ret == 1 && buf[0] == 'a');
+
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &default_term));
+}
+END_TEST()
+
+FN_TEST(switch_to_canon)
+{
+	struct termios term = default_term;
+	char buf[64];
+
+	term.c_lflag &= ~(ICANON | ECHO);
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &term));
+	TEST_RES(write(master, "ab", 2), _ret == 2);
+	TEST_SUCC(wait_input());
+
+	// The pending characters become a line that can be read immediately.
+	term.c_lflag |= ICANON;
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &term));
+	TEST_RES(read(slave, buf, sizeof(buf)),
+		 _ret == 2 && memcmp(buf, "ab", 2) == 0);
+
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &default_term));
+}
+END_TEST()
+
+FN_TEST(vmin_vtime)
+{
+	struct pollfd pfd = { .fd = slave, .events = POLLIN };
+	struct termios term = default_term;
+	int64_t start;
+	char buf[64];
+
+	term.c_lflag &= ~(ICANON | ECHO);
+
+	// `VMIN = 0` and `VTIME > 0`: Wait for the first byte until the timer expires.
+	term.c_cc[VMIN] = 0;
+	term.c_cc[VTIME] = 2;
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &term));
+	start = now_ms();
+	TEST_RES(read(slave, buf, sizeof(buf)), _ret == 0);
+	TEST_RES(now_ms() - start, _ret >= 150 && _ret < 1000);
+
+	TEST_RES(write(master, "a", 1), _ret == 1);
+	TEST_RES(read(slave, buf, sizeof(buf)), _ret == 1 && buf[0] == 'a');
+
+	// `VMIN = 0` and `VTIME = 0`: Never wait.
+	term.c_cc[VTIME] = 0;
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &term));
+	TEST_RES(read(slave, buf, sizeof(buf)), _ret == 0);
+
+	// `VMIN > 0` and `VTIME > 0`: Wait for `VMIN` bytes until the inter-byte timer expires.
+	term.c_cc[VMIN] = 3;
+	term.c_cc[VTIME] = 2;
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &term));
+	TEST_RES(write(master, "ab", 2), _ret == 2);
+	start = now_ms();
+	TEST_RES(read(slave, buf, sizeof(buf)),
+		 _ret == 2 && memcmp(buf, "ab", 2) == 0);
+	TEST_RES(now_ms() - start, _ret >= 150 && _ret < 1000);
+
+	TEST_RES(write(master, "abc", 3), _ret == 3);
+	TEST_SUCC(wait_input());
+	usleep(10000);
+	TEST_RES(read(slave, buf, sizeof(buf)),
+		 _ret == 3 && memcmp(buf, "abc", 3) == 0);
+
+	// `VMIN > 0` and `VTIME = 0`: The input is not readable for polling until `VMIN` bytes
+	// arrive, but non-blocking reads return what is available.
+	term.c_cc[VTIME] = 0;
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &term));
+	TEST_SUCC(fcntl(slave, F_SETFL, O_NONBLOCK));
+	TEST_ERRNO(read(slave, buf, sizeof(buf)), EAGAIN);
+	TEST_RES(write(master, "a", 1), _ret == 1);
+	usleep(10000);
+	TEST_RES(poll(&pfd, 1, 100), _ret == 0);
+	TEST_RES(read(slave, buf, sizeof(buf)), _ret == 1 && buf[0] == 'a');
+	TEST_SUCC(fcntl(slave, F_SETFL, 0));
+
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &default_term));
+}
+END_TEST()
+
+FN_SETUP(close_pty)
+{
+	CHECK(close(master));
+	CHECK(close(slave));
+}
+END_SETUP()
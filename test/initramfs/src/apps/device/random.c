@@ -1,5 +1,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
+#include <ctype.h>
+#include <string.h>
 #include <unistd.h>
 #include <sys/mman.h>
 #include <sys/fcntl.h>
@@ -44,3 +46,116 @@ FN_TEST(short_rw)
 	TEST_SUCC(close(fd));
 }
 END_TEST()
+
+FN_TEST(write)
+{
+	char buf[64] = {};
+	char *bad_buf;
+	int fd;
+
+	bad_buf = TEST_SUCC(mmap(NULL, PAGE_SIZE, PROT_READ | PROT_WRITE,
+				 MAP_ANONYMOUS | MAP_PRIVATE, -1, 0));
+	TEST_SUCC(munmap(bad_buf, PAGE_SIZE));
+
+	// The written data is mixed into the input pool.
+	fd = TEST_SUCC(open("/dev/random", O_WRONLY));
+	TEST_RES(write(fd, buf, sizeof(buf)), _ret == sizeof(buf));
+	TEST_ERRNO(write(fd, bad_buf, sizeof(buf)), EFAULT);
+	TEST_SUCC(close(fd));
+
+	fd = TEST_SUCC(open("/dev/urandom", O_WRONLY));
+	TEST_RES(write(fd, buf, sizeof(buf)), _ret == sizeof(buf));
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(hwrng)
+{
+	char buf[100];
+	int fd;
+
+	fd = TEST_SUCC(open("/dev/hwrng", O_RDONLY));
+	TEST_RES(read(fd, buf, sizeof(buf)), _ret == sizeof(buf));
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+static ssize_t read_file(const char *path, char *buf, size_t len)
+{
+	ssize_t ret;
+	int fd;
+
+	fd = open(path, O_RDONLY);
+	if (fd < 0)
+		return -1;
+	ret = read(fd, buf, len - 1);
+	close(fd);
+
+	if (ret >= 0)
+		buf[ret] = '\0';
+	return ret;
+}
+
+static int write_file(const char *path, const char *buf)
+{
+	ssize_t ret;
+	int fd;
+
+	fd = open(path, O_WRONLY);
+	if (fd < 0)
+		return -1;
+	ret = write(fd, buf, strlen(buf));
+	close(fd);
+
+	return ret < 0 ? -1 : 0;
+}
+
+static int is_uuid(const char *buf)
+{
+	int i;
+
+	for (i = 0; i < 36; i++) {
+		if (i == 8 || i == 13 || i == 18 || i == 23) {
+			if (buf[i] != '-')
+				return 0;
+		} else if (!isxdigit(buf[i]) || isupper(buf[i])) {
+			return 0;
+		}
+	}
+
+	// The version and the variant of random UUIDs
+	return buf[14] == '4' && strchr("89ab", buf[19]) && buf[36] == '\n';
+}
+
+#define RANDOM_SYSCTL "/proc/sys/kernel/random/"
+
+FN_TEST(sysctl)
+{
+	char buf[64], buf2[64];
+
+	TEST_RES(read_file(RANDOM_SYSCTL "poolsize", buf, sizeof(buf)),
+		 strcmp(buf, "256\n") == 0);
+	TEST_RES(read_file(RANDOM_SYSCTL "entropy_avail", buf, sizeof(buf)),
+		 strcmp(buf, "256\n") == 0);
+
+	TEST_RES(read_file(RANDOM_SYSCTL "boot_id", buf, sizeof(buf)),
+		 _ret == 37 && is_uuid(buf));
+	TEST_RES(read_file(RANDOM_SYSCTL "boot_id", buf2, sizeof(buf2)),
+		 _ret == 37 && strcmp(buf, buf2) == 0);
+
+	TEST_RES(read_file(RANDOM_SYSCTL "uuid", buf, sizeof(buf)),
+		 _ret == 37 && is_uuid(buf));
+	TEST_RES(read_file(RANDOM_SYSCTL "uuid", buf2, sizeof(buf2)),
+		 _ret == 37 && is_uuid(buf2) && strcmp(buf, buf2) != 0);
+
+	TEST_RES(read_file(RANDOM_SYSCTL "urandom_min_reseed_secs", buf,
+			   sizeof(buf)),
+		 strcmp(buf, "60\n") == 0);
+	TEST_SUCC(write_file(RANDOM_SYSCTL "urandom_min_reseed_secs", "60\n"));
+
+	TEST_RES(read_file(RANDOM_SYSCTL "write_wakeup_threshold", buf,
+			   sizeof(buf)),
+		 strcmp(buf, "256\n") == 0);
+	TEST_SUCC(write_file(RANDOM_SYSCTL "write_wakeup_threshold", "256\n"));
+}
+END_TEST()
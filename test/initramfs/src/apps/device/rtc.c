@@ -0,0 +1,175 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <linux/rtc.h>
+#include <poll.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/ioctl.h>
+#include <time.h>
+#include <unistd.h>
+
+#include "../common/test.h"
+
+#define RTC_DEVICE "/dev/rtc0"
+
+static int rtc_fd = -1;
+
+static time_t rtc_time_to_secs(const struct rtc_time *tm)
+{
+	struct tm t = {
+		.tm_sec = tm->tm_sec,
+		.tm_min = tm->tm_min,
+		.tm_hour = tm->tm_hour,
+		.tm_mday = tm->tm_mday,
+		.tm_mon = tm->tm_mon,
+		.tm_year = tm->tm_year,
+	};
+
+	return timegm(&t);
+}
+
+static void secs_to_rtc_time(time_t secs, struct rtc_time *tm)
+{
+	struct tm t;
+
+	gmtime_r(&secs, &t);
+	memset(tm, 0, sizeof(*tm));
+	tm->tm_sec = t.tm_sec;
+	tm->tm_min = t.tm_min;
+	tm->tm_hour = t.tm_hour;
+	tm->tm_mday = t.tm_mday;
+	tm->tm_mon = t.tm_mon;
+	tm->tm_year = t.tm_year;
+}
+
+FN_SETUP(open_rtc)
+{
+	rtc_fd = open(RTC_DEVICE, O_RDONLY);
+	if (rtc_fd < 0 && errno == ENOENT) {
+		fprintf(stderr, "RTC tests skipped: %s does not exist\n",
+			RTC_DEVICE);
+		exit(EXIT_SUCCESS);
+	}
+	CHECK(rtc_fd);
+}
+END_SETUP()
+
+FN_TEST(open_exclusive)
+{
+	TEST_ERRNO(open(RTC_DEVICE, O_RDONLY), EBUSY);
+}
+END_TEST()
+
+FN_TEST(read_time)
+{
+	struct rtc_time tm;
+	time_t now;
+
+	TEST_RES(ioctl(rtc_fd, RTC_RD_TIME, &tm),
+		 tm.tm_year >= 70 && tm.tm_mon >= 0 && tm.tm_mon < 12 &&
+			 tm.tm_mday >= 1 && tm.tm_mday <= 31 &&
+			 tm.tm_hour < 24 && tm.tm_min < 60 && tm.tm_sec < 60);
+
+	// The system time is initialized from the RTC at boot.
+	now = time(NULL);
+	TEST_RES(0, rtc_time_to_secs(&tm) <= now + 2 &&
+			    rtc_time_to_secs(&tm) >= now - 2);
+}
+END_TEST()
+
+FN_TEST(set_time)
+{
+	struct rtc_time tm;
+
+	TEST_SUCC(ioctl(rtc_fd, RTC_RD_TIME, &tm));
+	TEST_SUCC(ioctl(rtc_fd, RTC_SET_TIME, &tm));
+
+	tm.tm_mon = 12;
+	TEST_ERRNO(ioctl(rtc_fd, RTC_SET_TIME, &tm), EINVAL);
+	tm.tm_mon = 1;
+	tm.tm_mday = 30;
+	TEST_ERRNO(ioctl(rtc_fd, RTC_SET_TIME, &tm), EINVAL);
+	tm.tm_mday = 1;
+	tm.tm_year = 69;
+	TEST_ERRNO(ioctl(rtc_fd, RTC_SET_TIME, &tm), EINVAL);
+}
+END_TEST()
+
+FN_TEST(read_without_events)
+{
+	unsigned long data;
+	uint16_t short_data;
+	int flags;
+
+	flags = TEST_SUCC(fcntl(rtc_fd, F_GETFL));
+	TEST_SUCC(fcntl(rtc_fd, F_SETFL, flags | O_NONBLOCK));
+	TEST_ERRNO(read(rtc_fd, &data, sizeof(data)), EAGAIN);
+	TEST_ERRNO(read(rtc_fd, &short_data, sizeof(short_data)), EINVAL);
+	TEST_SUCC(fcntl(rtc_fd, F_SETFL, flags));
+}
+END_TEST()
+
+FN_TEST(alarm)
+{
+	struct pollfd pfd = { .fd = rtc_fd, .events = POLLIN };
+	struct rtc_wkalrm alarm;
+	struct rtc_time tm;
+	unsigned long data;
+
+	TEST_SUCC(ioctl(rtc_fd, RTC_RD_TIME, &tm));
+	secs_to_rtc_time(rtc_time_to_secs(&tm) + 2, &alarm.time);
+	alarm.enabled = 1;
+	TEST_SUCC(ioctl(rtc_fd, RTC_WKALM_SET, &alarm));
+
+	TEST_RES(ioctl(rtc_fd, RTC_WKALM_RD, &alarm), alarm.enabled == 1);
+	TEST_RES(poll(&pfd, 1, 0), _ret == 0);
+
+	TEST_RES(read(rtc_fd, &data, sizeof(data)),
+		 _ret == sizeof(data) && (data & RTC_AF) && (data & RTC_IRQF) &&
+			 (data >> 8) == 1);
+	TEST_RES(ioctl(rtc_fd, RTC_WKALM_RD, &alarm), alarm.enabled == 0);
+	TEST_RES(poll(&pfd, 1, 0), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(alarm_in_the_past)
+{
+	struct rtc_wkalrm alarm;
+	struct rtc_time tm;
+	unsigned int data;
+
+	// An enabled alarm in the past fires immediately.
+	TEST_SUCC(ioctl(rtc_fd, RTC_RD_TIME, &tm));
+	secs_to_rtc_time(rtc_time_to_secs(&tm) - 60, &alarm.time);
+	alarm.enabled = 1;
+	TEST_SUCC(ioctl(rtc_fd, RTC_WKALM_SET, &alarm));
+	TEST_RES(read(rtc_fd, &data, sizeof(data)),
+		 _ret == sizeof(data) && (data >> 8) == 1);
+
+	// `RTC_ALM_SET` disables the alarm and `RTC_AIE_ON` enables it.
+	TEST_SUCC(ioctl(rtc_fd, RTC_ALM_SET, &alarm.time));
+	TEST_RES(ioctl(rtc_fd, RTC_WKALM_RD, &alarm), alarm.enabled == 0);
+	TEST_SUCC(ioctl(rtc_fd, RTC_AIE_OFF));
+	TEST_RES(ioctl(rtc_fd, RTC_ALM_READ, &tm),
+		 tm.tm_hour == alarm.time.tm_hour &&
+			 tm.tm_min == alarm.time.tm_min &&
+			 tm.tm_sec == alarm.time.tm_sec);
+
+	// The alarm is set within the next 24 hours, so it does not fire.
+	TEST_SUCC(ioctl(rtc_fd, RTC_AIE_ON));
+	TEST_RES(ioctl(rtc_fd, RTC_WKALM_RD, &alarm), alarm.enabled == 1);
+	TEST_SUCC(ioctl(rtc_fd, RTC_AIE_OFF));
+	TEST_RES(ioctl(rtc_fd, RTC_WKALM_RD, &alarm), alarm.enabled == 0);
+}
+END_TEST()
+
+FN_SETUP(close_rtc)
+{
+	CHECK(close(rtc_fd));
+}
+END_SETUP()
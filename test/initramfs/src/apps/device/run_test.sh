@@ -9,7 +9,21 @@ set -e
 ./pty/open_pty
 ./pty/pty_blocking
 ./pty/pty_packet_mode
+./pty/pty_job_control
+./pty/pty_ldisc
+./devtmpfs
 ./evdev
 ./framebuffer
 ./full
+./kmsg
+./loop
+./discard
+./partition
+./dm
+./drm
 ./random
+./rtc
+./serial
+./snd
+./virtio_port
+./watchdog
@@ -0,0 +1,104 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <sys/ioctl.h>
+#include <termios.h>
+#include <unistd.h>
+
+#include "../common/test.h"
+
+#define SERIAL_DEVICE "/dev/ttyS0"
+
+static int fd = -1;
+static struct termios old_term;
+
+FN_SETUP(open_serial)
+{
+	fd = open(SERIAL_DEVICE, O_RDWR | O_NOCTTY | O_NONBLOCK);
+	if (fd < 0 && errno == ENOENT) {
+		fprintf(stderr, "serial tests skipped: %s does not exist\n",
+			SERIAL_DEVICE);
+		exit(EXIT_SUCCESS);
+	}
+	CHECK(fd);
+
+	CHECK(tcgetattr(fd, &old_term));
+}
+END_SETUP()
+
+FN_TEST(baud_rate)
+{
+	struct termios term;
+
+	term = old_term;
+	TEST_SUCC(cfsetspeed(&term, B9600));
+	TEST_SUCC(tcsetattr(fd, TCSANOW, &term));
+	TEST_RES(tcgetattr(fd, &term),
+		 cfgetospeed(&term) == B9600 && cfgetispeed(&term) == B9600);
+
+	TEST_SUCC(cfsetspeed(&term, B115200));
+	TEST_SUCC(tcsetattr(fd, TCSANOW, &term));
+	TEST_RES(tcgetattr(fd, &term), cfgetospeed(&term) == B115200);
+
+	term.c_cflag &= ~CSIZE;
+	term.c_cflag |= CS7 | PARENB | PARODD | CSTOPB;
+	TEST_SUCC(tcsetattr(fd, TCSANOW, &term));
+	TEST_RES(tcgetattr(fd, &term),
+		 (term.c_cflag & CSIZE) == CS7 &&
+			 (term.c_cflag & (PARENB | PARODD | CSTOPB)) ==
+				 (PARENB | PARODD | CSTOPB));
+
+	TEST_SUCC(tcsetattr(fd, TCSANOW, &old_term));
+}
+END_TEST()
+
+FN_TEST(modem_lines)
+{
+	int lines;
+
+	lines = TIOCM_DTR;
+	TEST_SUCC(ioctl(fd, TIOCMBIC, &lines));
+	TEST_RES(ioctl(fd, TIOCMGET, &lines), !(lines & TIOCM_DTR));
+
+	lines = TIOCM_DTR;
+	TEST_SUCC(ioctl(fd, TIOCMBIS, &lines));
+	TEST_RES(ioctl(fd, TIOCMGET, &lines), lines & TIOCM_DTR);
+
+	lines = TIOCM_DTR;
+	TEST_SUCC(ioctl(fd, TIOCMSET, &lines));
+	TEST_RES(ioctl(fd, TIOCMGET, &lines),
+		 (lines & TIOCM_DTR) && !(lines & TIOCM_RTS));
+
+	lines = TIOCM_DTR | TIOCM_RTS;
+	TEST_SUCC(ioctl(fd, TIOCMSET, &lines));
+	TEST_RES(ioctl(fd, TIOCMGET, &lines),
+		 (lines & (TIOCM_DTR | TIOCM_RTS)) == (TIOCM_DTR | TIOCM_RTS));
+}
+END_TEST()
+
+FN_TEST(hang_up)
+{
+	struct termios term;
+	int lines;
+
+	// Setting the baud rate to zero drops DTR.
+	term = old_term;
+	TEST_SUCC(cfsetospeed(&term, B0));
+	TEST_SUCC(tcsetattr(fd, TCSANOW, &term));
+	TEST_RES(ioctl(fd, TIOCMGET, &lines), !(lines & TIOCM_DTR));
+
+	// Restoring the baud rate raises DTR again.
+	TEST_SUCC(tcsetattr(fd, TCSANOW, &old_term));
+	TEST_RES(ioctl(fd, TIOCMGET, &lines), lines & TIOCM_DTR);
+}
+END_TEST()
+
+FN_SETUP(close_serial)
+{
+	CHECK(close(fd));
+}
+END_SETUP()
@@ -0,0 +1,241 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <limits.h>
+#include <sound/asound.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/ioctl.h>
+#include <unistd.h>
+
+#include "../common/test.h"
+
+#define CONTROL_DEVICE "/dev/snd/controlC0"
+#define PLAYBACK_DEVICE "/dev/snd/pcmC0D0p"
+
+#define CHANNELS 2
+#define RATE 48000
+#define PERIOD_FRAMES 1024
+#define BUFFER_FRAMES 4096
+#define FRAME_BYTES (CHANNELS * 2)
+
+static int ctl_fd = -1;
+static int pcm_fd = -1;
+
+static int16_t frames[BUFFER_FRAMES * CHANNELS];
+
+static struct snd_mask *hw_mask(struct snd_pcm_hw_params *params, int param)
+{
+	return &params->masks[param - SNDRV_PCM_HW_PARAM_FIRST_MASK];
+}
+
+static struct snd_interval *hw_interval(struct snd_pcm_hw_params *params,
+					int param)
+{
+	return &params->intervals[param - SNDRV_PCM_HW_PARAM_FIRST_INTERVAL];
+}
+
+static void hw_params_any(struct snd_pcm_hw_params *params)
+{
+	int i;
+
+	memset(params, 0, sizeof(*params));
+	for (i = SNDRV_PCM_HW_PARAM_FIRST_MASK;
+	     i <= SNDRV_PCM_HW_PARAM_LAST_MASK; i++)
+		memset(hw_mask(params, i), 0xff, sizeof(struct snd_mask));
+	for (i = SNDRV_PCM_HW_PARAM_FIRST_INTERVAL;
+	     i <= SNDRV_PCM_HW_PARAM_LAST_INTERVAL; i++) {
+		hw_interval(params, i)->min = 0;
+		hw_interval(params, i)->max = UINT_MAX;
+	}
+	params->rmask = ~0U;
+}
+
+static void hw_params_set_mask(struct snd_pcm_hw_params *params, int param,
+			       unsigned int value)
+{
+	struct snd_mask *mask = hw_mask(params, param);
+
+	memset(mask, 0, sizeof(*mask));
+	mask->bits[value / 32] = 1U << (value % 32);
+}
+
+static void hw_params_set_interval(struct snd_pcm_hw_params *params, int param,
+				   unsigned int value)
+{
+	struct snd_interval *interval = hw_interval(params, param);
+
+	interval->min = value;
+	interval->max = value;
+}
+
+FN_SETUP(open_devices)
+{
+	ctl_fd = open(CONTROL_DEVICE, O_RDWR);
+	if (ctl_fd < 0 && errno == ENOENT) {
+		fprintf(stderr, "sound tests skipped: %s does not exist\n",
+			CONTROL_DEVICE);
+		exit(EXIT_SUCCESS);
+	}
+	CHECK(ctl_fd);
+
+	pcm_fd = open(PLAYBACK_DEVICE, O_RDWR);
+	if (pcm_fd < 0 && errno == ENOENT) {
+		fprintf(stderr, "sound tests skipped: %s does not exist\n",
+			PLAYBACK_DEVICE);
+		exit(EXIT_SUCCESS);
+	}
+	CHECK(pcm_fd);
+}
+END_SETUP()
+
+FN_TEST(control)
+{
+	int version;
+	int device;
+	struct snd_ctl_card_info card_info = {};
+	struct snd_pcm_info pcm_info = {};
+
+	TEST_RES(ioctl(ctl_fd, SNDRV_CTL_IOCTL_PVERSION, &version),
+		 SNDRV_PROTOCOL_MAJOR(version) == 2);
+	TEST_RES(ioctl(ctl_fd, SNDRV_CTL_IOCTL_CARD_INFO, &card_info),
+		 card_info.card == 0 &&
+			 strcmp((char *)card_info.driver, "virtio-snd") == 0);
+
+	device = -1;
+	TEST_RES(ioctl(ctl_fd, SNDRV_CTL_IOCTL_PCM_NEXT_DEVICE, &device),
+		 device == 0);
+
+	pcm_info.device = 0;
+	pcm_info.stream = SNDRV_PCM_STREAM_PLAYBACK;
+	TEST_RES(ioctl(ctl_fd, SNDRV_CTL_IOCTL_PCM_INFO, &pcm_info),
+		 pcm_info.device == 0 && pcm_info.subdevices_count == 1 &&
+			 pcm_info.subdevices_avail == 0);
+
+	pcm_info.subdevice = 1;
+	TEST_ERRNO(ioctl(ctl_fd, SNDRV_CTL_IOCTL_PCM_INFO, &pcm_info), ENXIO);
+
+	// There are no control elements, so there are no events.
+	TEST_ERRNO(read(ctl_fd, &version, sizeof(version)), EBADFD);
+}
+END_TEST()
+
+FN_TEST(pcm_info)
+{
+	int version;
+	struct snd_pcm_info info = {};
+
+	TEST_RES(ioctl(pcm_fd, SNDRV_PCM_IOCTL_PVERSION, &version),
+		 SNDRV_PROTOCOL_MAJOR(version) == 2);
+	TEST_RES(ioctl(pcm_fd, SNDRV_PCM_IOCTL_INFO, &info),
+		 info.card == 0 && info.device == 0 &&
+			 info.stream == SNDRV_PCM_STREAM_PLAYBACK);
+
+	// Each PCM device has only one substream.
+	TEST_ERRNO(open(PLAYBACK_DEVICE, O_RDWR), EBUSY);
+
+	// The hardware parameters are not set yet.
+	TEST_ERRNO(ioctl(pcm_fd, SNDRV_PCM_IOCTL_PREPARE), EBADFD);
+}
+END_TEST()
+
+FN_TEST(hw_params)
+{
+	struct snd_pcm_hw_params params;
+
+	hw_params_any(&params);
+	TEST_RES(ioctl(pcm_fd, SNDRV_PCM_IOCTL_HW_REFINE, &params),
+		 hw_interval(&params, SNDRV_PCM_HW_PARAM_CHANNELS)->min >= 1 &&
+			 hw_interval(&params, SNDRV_PCM_HW_PARAM_RATE)->min > 0);
+
+	hw_params_any(&params);
+	hw_params_set_interval(&params, SNDRV_PCM_HW_PARAM_CHANNELS, 0);
+	TEST_ERRNO(ioctl(pcm_fd, SNDRV_PCM_IOCTL_HW_REFINE, &params), EINVAL);
+
+	hw_params_any(&params);
+	hw_params_set_mask(&params, SNDRV_PCM_HW_PARAM_ACCESS,
+			   SNDRV_PCM_ACCESS_RW_INTERLEAVED);
+	hw_params_set_mask(&params, SNDRV_PCM_HW_PARAM_FORMAT,
+			   SNDRV_PCM_FORMAT_S16_LE);
+	hw_params_set_interval(&params, SNDRV_PCM_HW_PARAM_CHANNELS, CHANNELS);
+	hw_params_set_interval(&params, SNDRV_PCM_HW_PARAM_RATE, RATE);
+	hw_params_set_interval(&params, SNDRV_PCM_HW_PARAM_PERIOD_SIZE,
+			       PERIOD_FRAMES);
+	hw_params_set_interval(&params, SNDRV_PCM_HW_PARAM_BUFFER_SIZE,
+			       BUFFER_FRAMES);
+	TEST_RES(ioctl(pcm_fd, SNDRV_PCM_IOCTL_HW_PARAMS, &params),
+		 hw_interval(&params, SNDRV_PCM_HW_PARAM_FRAME_BITS)->min ==
+				 FRAME_BYTES * 8 &&
+			 hw_interval(&params, SNDRV_PCM_HW_PARAM_PERIODS)->min ==
+				 BUFFER_FRAMES / PERIOD_FRAMES);
+}
+END_TEST()
+
+FN_TEST(sw_params)
+{
+	struct snd_pcm_sw_params params = {
+		.avail_min = PERIOD_FRAMES,
+		.start_threshold = BUFFER_FRAMES,
+		.stop_threshold = BUFFER_FRAMES,
+	};
+
+	TEST_RES(ioctl(pcm_fd, SNDRV_PCM_IOCTL_SW_PARAMS, &params),
+		 params.boundary >= BUFFER_FRAMES &&
+			 params.boundary % BUFFER_FRAMES == 0);
+
+	params.avail_min = 0;
+	params.tstamp_mode = 2;
+	TEST_ERRNO(ioctl(pcm_fd, SNDRV_PCM_IOCTL_SW_PARAMS, &params), EINVAL);
+}
+END_TEST()
+
+FN_TEST(playback)
+{
+	struct snd_xferi xferi = {
+		.buf = frames,
+		.frames = BUFFER_FRAMES,
+	};
+	struct snd_pcm_status status = {};
+	int i;
+
+	for (i = 0; i < BUFFER_FRAMES * CHANNELS; i++)
+		frames[i] = (i % 64) * 256;
+
+	TEST_SUCC(ioctl(pcm_fd, SNDRV_PCM_IOCTL_PREPARE));
+	TEST_RES(ioctl(pcm_fd, SNDRV_PCM_IOCTL_STATUS, &status),
+		 status.state == SNDRV_PCM_STATE_PREPARED &&
+			 status.avail == BUFFER_FRAMES);
+
+	// Filling the buffer reaches the start threshold, so the stream is started.
+	TEST_RES(ioctl(pcm_fd, SNDRV_PCM_IOCTL_WRITEI_FRAMES, &xferi),
+		 xferi.result == BUFFER_FRAMES);
+	TEST_RES(ioctl(pcm_fd, SNDRV_PCM_IOCTL_STATUS, &status),
+		 status.state == SNDRV_PCM_STATE_RUNNING);
+
+	TEST_SUCC(ioctl(pcm_fd, SNDRV_PCM_IOCTL_DRAIN));
+	TEST_RES(ioctl(pcm_fd, SNDRV_PCM_IOCTL_STATUS, &status),
+		 status.state == SNDRV_PCM_STATE_SETUP);
+
+	// The frames can also be written with `write`.
+	TEST_SUCC(ioctl(pcm_fd, SNDRV_PCM_IOCTL_PREPARE));
+	TEST_ERRNO(write(pcm_fd, frames, FRAME_BYTES + 1), EINVAL);
+	TEST_RES(write(pcm_fd, frames, PERIOD_FRAMES * FRAME_BYTES),
+		 _ret == PERIOD_FRAMES * FRAME_BYTES);
+	TEST_SUCC(ioctl(pcm_fd, SNDRV_PCM_IOCTL_START));
+	TEST_SUCC(ioctl(pcm_fd, SNDRV_PCM_IOCTL_DROP));
+	TEST_RES(ioctl(pcm_fd, SNDRV_PCM_IOCTL_STATUS, &status),
+		 status.state == SNDRV_PCM_STATE_SETUP);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(ioctl(pcm_fd, SNDRV_PCM_IOCTL_HW_FREE));
+	CHECK(close(pcm_fd));
+	CHECK(close(ctl_fd));
+}
+END_SETUP()
@@ -0,0 +1,80 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <poll.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+
+#include "../common/test.h"
+
+// The port is added by `tools/qemu_args.sh` with a null backend, so its host side is never
+// connected.
+#define PORT_NAME "org.asterinas.test.0"
+#define PORT_LINK "/dev/virtio-ports/" PORT_NAME
+
+static int fd = -1;
+
+FN_SETUP(open_port)
+{
+	fd = open(PORT_LINK, O_RDWR | O_NONBLOCK);
+	if (fd < 0 && errno == ENOENT) {
+		fprintf(stderr, "virtio port tests skipped: %s does not exist\n",
+			PORT_LINK);
+		exit(EXIT_SUCCESS);
+	}
+	CHECK(fd);
+}
+END_SETUP()
+
+FN_TEST(port_link)
+{
+	char target[64] = {};
+	char path[64];
+
+	TEST_RES(readlink(PORT_LINK, target, sizeof(target) - 1),
+		 strncmp(target, "../vport", 8) == 0);
+
+	snprintf(path, sizeof(path), "/dev/%s", target + 3);
+	TEST_SUCC(access(path, F_OK));
+}
+END_TEST()
+
+FN_TEST(open_twice)
+{
+	TEST_ERRNO(open(PORT_LINK, O_RDWR), EBUSY);
+}
+END_TEST()
+
+FN_TEST(host_disconnected)
+{
+	struct pollfd pfd = { .fd = fd, .events = POLLIN | POLLOUT };
+	char buf[16];
+
+	// Reading returns the end of file and writing blocks if the host side is not connected.
+	TEST_RES(read(fd, buf, sizeof(buf)), _ret == 0);
+	TEST_ERRNO(write(fd, "hello", 5), EAGAIN);
+
+	TEST_RES(poll(&pfd, 1, 0),
+		 _ret == 1 && pfd.revents == (POLLIN | POLLHUP));
+}
+END_TEST()
+
+FN_TEST(console_port)
+{
+	// Port 0 is the console port if `hvc0` exists, which can only be opened as `hvc0`.
+	if (access("/dev/hvc0", F_OK) == 0 &&
+	    access("/dev/vport0p0", F_OK) == 0) {
+		TEST_ERRNO(open("/dev/vport0p0", O_RDWR), ENXIO);
+	}
+}
+END_TEST()
+
+FN_SETUP(close_port)
+{
+	CHECK(close(fd));
+}
+END_SETUP()
@@ -0,0 +1,134 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <linux/watchdog.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/ioctl.h>
+#include <unistd.h>
+
+#include "../common/test.h"
+
+#define WATCHDOG_DEVICE "/dev/watchdog"
+
+// The watchdog timer must be stopped with the magic character before the device is closed.
+// Otherwise, the system will be reset.
+static int wd_fd = -1;
+
+FN_SETUP(open_watchdog)
+{
+	wd_fd = open(WATCHDOG_DEVICE, O_WRONLY);
+	if (wd_fd < 0 && errno == ENOENT) {
+		fprintf(stderr, "watchdog tests skipped: %s does not exist\n",
+			WATCHDOG_DEVICE);
+		exit(EXIT_SUCCESS);
+	}
+	CHECK(wd_fd);
+}
+END_SETUP()
+
+FN_TEST(open_exclusive)
+{
+	TEST_ERRNO(open(WATCHDOG_DEVICE, O_WRONLY), EBUSY);
+}
+END_TEST()
+
+FN_TEST(get_support)
+{
+	struct watchdog_info info;
+	int status;
+
+	TEST_RES(ioctl(wd_fd, WDIOC_GETSUPPORT, &info),
+		 (info.options & WDIOF_SETTIMEOUT) &&
+			 (info.options & WDIOF_KEEPALIVEPING) &&
+			 (info.options & WDIOF_MAGICCLOSE) &&
+			 strnlen((char *)info.identity,
+				 sizeof(info.identity)) > 0 &&
+			 strnlen((char *)info.identity,
+				 sizeof(info.identity)) <
+				 sizeof(info.identity));
+	TEST_SUCC(ioctl(wd_fd, WDIOC_GETBOOTSTATUS, &status));
+}
+END_TEST()
+
+FN_TEST(timeout)
+{
+	int timeout;
+
+	timeout = 0;
+	TEST_ERRNO(ioctl(wd_fd, WDIOC_SETTIMEOUT, &timeout), EINVAL);
+	timeout = -1;
+	TEST_ERRNO(ioctl(wd_fd, WDIOC_SETTIMEOUT, &timeout), EINVAL);
+
+	timeout = 120;
+	TEST_RES(ioctl(wd_fd, WDIOC_SETTIMEOUT, &timeout), timeout == 120);
+	TEST_RES(ioctl(wd_fd, WDIOC_GETTIMEOUT, &timeout), timeout == 120);
+
+	// Not all watchdog timers can report the remaining time.
+	if (ioctl(wd_fd, WDIOC_GETTIMELEFT, &timeout) == 0) {
+		TEST_RES(0, timeout <= 120);
+	} else {
+		int err = errno;
+		TEST_RES(0, err == EOPNOTSUPP);
+	}
+}
+END_TEST()
+
+FN_TEST(keepalive)
+{
+	int status;
+
+	TEST_SUCC(ioctl(wd_fd, WDIOC_GETSTATUS, &status));
+	TEST_RES(ioctl(wd_fd, WDIOC_GETSTATUS, &status),
+		 !(status & WDIOF_KEEPALIVEPING));
+
+	TEST_SUCC(ioctl(wd_fd, WDIOC_KEEPALIVE, 0));
+	TEST_RES(ioctl(wd_fd, WDIOC_GETSTATUS, &status),
+		 status & WDIOF_KEEPALIVEPING);
+	TEST_RES(ioctl(wd_fd, WDIOC_GETSTATUS, &status),
+		 !(status & WDIOF_KEEPALIVEPING));
+
+	TEST_RES(write(wd_fd, "x", 1), _ret == 1);
+	TEST_RES(ioctl(wd_fd, WDIOC_GETSTATUS, &status),
+		 (status & WDIOF_KEEPALIVEPING) && !(status & WDIOF_MAGICCLOSE));
+}
+END_TEST()
+
+FN_TEST(magic_close)
+{
+	int status;
+
+	TEST_RES(write(wd_fd, "xVx", 3), _ret == 3);
+	TEST_RES(ioctl(wd_fd, WDIOC_GETSTATUS, &status),
+		 status & WDIOF_MAGICCLOSE);
+
+	// Only the last write counts.
+	TEST_RES(write(wd_fd, "x", 1), _ret == 1);
+	TEST_RES(ioctl(wd_fd, WDIOC_GETSTATUS, &status),
+		 !(status & WDIOF_MAGICCLOSE));
+
+	TEST_RES(write(wd_fd, "V", 1), _ret == 1);
+	TEST_SUCC(close(wd_fd));
+}
+END_TEST()
+
+FN_TEST(disable_card)
+{
+	int options;
+
+	wd_fd = TEST_SUCC(open(WATCHDOG_DEVICE, O_WRONLY));
+
+	options = WDIOS_DISABLECARD;
+	TEST_SUCC(ioctl(wd_fd, WDIOC_SETOPTIONS, &options));
+	options = WDIOS_ENABLECARD;
+	TEST_SUCC(ioctl(wd_fd, WDIOC_SETOPTIONS, &options));
+	options = WDIOS_DISABLECARD;
+	TEST_SUCC(ioctl(wd_fd, WDIOC_SETOPTIONS, &options));
+
+	// The watchdog timer is stopped, so closing the device without the magic character is fine.
+	TEST_SUCC(close(wd_fd));
+}
+END_TEST()
@@ -0,0 +1,77 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/vfs.h>
+#include <unistd.h>
+
+#include "../../common/test.h"
+
+#define DEBUGFS_MAGIC 0x64626720
+
+static char buf[4096];
+
+static ssize_t read_file(const char *path)
+{
+	int fd;
+	ssize_t len;
+
+	fd = open(path, O_RDONLY);
+	if (fd < 0)
+		return -1;
+
+	len = read(fd, buf, sizeof(buf) - 1);
+	close(fd);
+	if (len >= 0)
+		buf[len] = '\0';
+
+	return len;
+}
+
+FN_TEST(statfs)
+{
+	struct statfs sfs;
+	struct stat st;
+
+	TEST_RES(statfs("/sys/kernel/debug", &sfs), sfs.f_type == DEBUGFS_MAGIC);
+	TEST_RES(stat("/sys/kernel/debug", &st),
+		 S_ISDIR(st.st_mode) && (st.st_mode & 0777) == 0700);
+}
+END_TEST()
+
+FN_TEST(sched)
+{
+	struct stat st;
+
+	TEST_RES(read_file("/sys/kernel/debug/sched/nr_running"),
+		 atoi(buf) >= 1 && buf[_ret - 1] == '\n');
+	TEST_RES(read_file("/sys/kernel/debug/sched/nr_queued"),
+		 buf[_ret - 1] == '\n');
+
+	TEST_RES(stat("/sys/kernel/debug/sched/nr_running", &st),
+		 S_ISREG(st.st_mode) && (st.st_mode & 0777) == 0444);
+}
+END_TEST()
+
+FN_TEST(net)
+{
+	TEST_RES(read_file("/sys/kernel/debug/net/lo/index"),
+		 strcmp(buf, "1\n") == 0);
+	TEST_RES(read_file("/sys/kernel/debug/net/lo/ipv4_addr"),
+		 strcmp(buf, "127.0.0.1\n") == 0);
+	TEST_RES(read_file("/sys/kernel/debug/net/lo/mtu"), atoi(buf) > 0);
+}
+END_TEST()
+
+FN_TEST(nonexistent)
+{
+	TEST_ERRNO(open("/sys/kernel/debug/sched/nonexistent", O_RDONLY),
+		   ENOENT);
+	TEST_ERRNO(open("/sys/kernel/debug/nonexistent", O_RDONLY), ENOENT);
+}
+END_TEST()
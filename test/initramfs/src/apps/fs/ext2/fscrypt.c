@@ -0,0 +1,252 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+#include <dirent.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <linux/fscrypt.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/ioctl.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#include "../../common/test.h"
+
+#define ENC_DIR "/ext2/test_fscrypt_dir"
+#define ENC_FILE ENC_DIR "/secret.txt"
+#define ENC_SUBDIR ENC_DIR "/subdir"
+#define PLAIN_DIR "/ext2/test_fscrypt_plain"
+#define PLAIN_FILE PLAIN_DIR "/file"
+
+#define FILE_CONTENT "The quick brown fox jumps over the lazy dog"
+
+static int root_fd;
+static struct fscrypt_key_specifier key_spec;
+
+static int add_key(void)
+{
+	struct {
+		struct fscrypt_add_key_arg arg;
+		__u8 raw[FSCRYPT_MAX_KEY_SIZE];
+	} buf;
+
+	memset(&buf, 0, sizeof(buf));
+	buf.arg.key_spec.type = FSCRYPT_KEY_SPEC_TYPE_IDENTIFIER;
+	buf.arg.raw_size = FSCRYPT_MAX_KEY_SIZE;
+	for (int i = 0; i < FSCRYPT_MAX_KEY_SIZE; i++)
+		buf.raw[i] = i * 7 + 1;
+
+	if (ioctl(root_fd, FS_IOC_ADD_ENCRYPTION_KEY, &buf.arg) < 0)
+		return -1;
+	key_spec = buf.arg.key_spec;
+	return 0;
+}
+
+static int key_status(void)
+{
+	struct fscrypt_get_key_status_arg arg;
+
+	memset(&arg, 0, sizeof(arg));
+	arg.key_spec = key_spec;
+	if (ioctl(root_fd, FS_IOC_GET_ENCRYPTION_KEY_STATUS, &arg) < 0)
+		return -1;
+	return arg.status;
+}
+
+static int set_policy(const char *path, __u8 version)
+{
+	struct fscrypt_policy_v2 policy;
+	int fd, ret;
+
+	memset(&policy, 0, sizeof(policy));
+	policy.version = version;
+	policy.contents_encryption_mode = FSCRYPT_MODE_AES_256_XTS;
+	policy.filenames_encryption_mode = FSCRYPT_MODE_AES_256_CTS;
+	policy.flags = FSCRYPT_POLICY_FLAGS_PAD_32;
+	memcpy(policy.master_key_identifier, key_spec.u.identifier,
+	       FSCRYPT_KEY_IDENTIFIER_SIZE);
+
+	fd = open(path, O_RDONLY);
+	if (fd < 0)
+		return -1;
+	ret = ioctl(fd, FS_IOC_SET_ENCRYPTION_POLICY, &policy);
+	close(fd);
+	return ret;
+}
+
+static int get_policy(const char *path, struct fscrypt_policy_v2 *policy)
+{
+	struct fscrypt_get_policy_ex_arg arg;
+	int fd, ret;
+
+	arg.policy_size = sizeof(arg.policy);
+	fd = open(path, O_RDONLY);
+	if (fd < 0)
+		return -1;
+	ret = ioctl(fd, FS_IOC_GET_ENCRYPTION_POLICY_EX, &arg);
+	close(fd);
+	if (ret < 0)
+		return -1;
+	if (arg.policy_size != sizeof(*policy)) {
+		errno = EINVAL;
+		return -1;
+	}
+	*policy = arg.policy.v2;
+	return 0;
+}
+
+static int get_nonce(const char *path, __u8 nonce[16])
+{
+	int fd, ret;
+
+	fd = open(path, O_RDONLY);
+	if (fd < 0)
+		return -1;
+	ret = ioctl(fd, FS_IOC_GET_ENCRYPTION_NONCE, nonce);
+	close(fd);
+	return ret;
+}
+
+// Returns the number of entries other than "." and ".." in the directory,
+// and whether an entry named `name` is among them.
+static int count_entries(const char *path, const char *name, int *found)
+{
+	struct dirent *entry;
+	int count = 0;
+	DIR *dir;
+
+	dir = opendir(path);
+	if (dir == NULL)
+		return -1;
+	*found = 0;
+	while ((entry = readdir(dir)) != NULL) {
+		if (strcmp(entry->d_name, ".") == 0 ||
+		    strcmp(entry->d_name, "..") == 0)
+			continue;
+		if (strcmp(entry->d_name, name) == 0)
+			*found = 1;
+		count++;
+	}
+	closedir(dir);
+	return count;
+}
+
+FN_SETUP(init)
+{
+	root_fd = CHECK(open("/ext2", O_RDONLY));
+	CHECK(mkdir(ENC_DIR, 0755));
+	CHECK(mkdir(PLAIN_DIR, 0755));
+	CHECK(add_key());
+}
+END_SETUP()
+
+FN_TEST(set_policy)
+{
+	int fd;
+
+	TEST_SUCC(key_status() == FSCRYPT_KEY_STATUS_PRESENT ? 0 : -1);
+
+	// The policy can only be set on an empty directory.
+	fd = TEST_SUCC(open(PLAIN_FILE, O_CREAT | O_WRONLY, 0644));
+	TEST_SUCC(close(fd));
+	TEST_ERRNO(set_policy(PLAIN_DIR, FSCRYPT_POLICY_V2), ENOTEMPTY);
+	TEST_ERRNO(set_policy(PLAIN_FILE, FSCRYPT_POLICY_V2), ENOTDIR);
+	TEST_ERRNO(set_policy(ENC_DIR, FSCRYPT_POLICY_V1), EINVAL);
+
+	TEST_ERRNO(get_nonce(ENC_DIR, (__u8[16]){}), ENODATA);
+	TEST_SUCC(set_policy(ENC_DIR, FSCRYPT_POLICY_V2));
+	// Setting the same policy again succeeds.
+	TEST_SUCC(set_policy(ENC_DIR, FSCRYPT_POLICY_V2));
+}
+END_TEST()
+
+FN_TEST(get_policy)
+{
+	struct fscrypt_policy_v2 policy;
+
+	TEST_RES(get_policy(ENC_DIR, &policy),
+		 policy.version == FSCRYPT_POLICY_V2 &&
+			 policy.contents_encryption_mode ==
+				 FSCRYPT_MODE_AES_256_XTS &&
+			 policy.filenames_encryption_mode ==
+				 FSCRYPT_MODE_AES_256_CTS &&
+			 memcmp(policy.master_key_identifier,
+				key_spec.u.identifier,
+				FSCRYPT_KEY_IDENTIFIER_SIZE) == 0);
+	TEST_ERRNO(get_policy(PLAIN_DIR, &policy), ENODATA);
+}
+END_TEST()
+
+FN_TEST(encrypted_files)
+{
+	struct fscrypt_policy_v2 policy;
+	__u8 dir_nonce[16], file_nonce[16];
+	char buf[sizeof(FILE_CONTENT)];
+	int fd, found;
+
+	fd = TEST_SUCC(open(ENC_FILE, O_CREAT | O_RDWR, 0644));
+	TEST_RES(write(fd, FILE_CONTENT, sizeof(FILE_CONTENT)),
+		 _ret == sizeof(FILE_CONTENT));
+	TEST_RES(pread(fd, buf, sizeof(buf), 0),
+		 _ret == sizeof(buf) && memcmp(buf, FILE_CONTENT, _ret) == 0);
+	TEST_SUCC(fsync(fd));
+	TEST_SUCC(close(fd));
+	TEST_SUCC(mkdir(ENC_SUBDIR, 0755));
+
+	// The children inherit the policy with their own nonces.
+	TEST_SUCC(get_policy(ENC_FILE, &policy));
+	TEST_SUCC(get_policy(ENC_SUBDIR, &policy));
+	TEST_SUCC(get_nonce(ENC_DIR, dir_nonce));
+	TEST_RES(get_nonce(ENC_FILE, file_nonce),
+		 memcmp(dir_nonce, file_nonce, sizeof(dir_nonce)) != 0);
+
+	TEST_RES(count_entries(ENC_DIR, "secret.txt", &found),
+		 _ret == 2 && found);
+
+	// Unencrypted files cannot be moved into an encrypted directory.
+	TEST_ERRNO(rename(PLAIN_FILE, ENC_DIR "/plain"), EXDEV);
+	TEST_ERRNO(link(PLAIN_FILE, ENC_DIR "/plain"), EXDEV);
+	TEST_ERRNO(symlink("secret.txt", ENC_DIR "/symlink"), EOPNOTSUPP);
+}
+END_TEST()
+
+FN_TEST(remove_key)
+{
+	struct fscrypt_remove_key_arg arg;
+	char buf[sizeof(FILE_CONTENT)];
+	int fd, found;
+
+	memset(&arg, 0, sizeof(arg));
+	arg.key_spec = key_spec;
+	TEST_RES(ioctl(root_fd, FS_IOC_REMOVE_ENCRYPTION_KEY, &arg),
+		 arg.removal_status_flags == 0);
+	TEST_RES(key_status(), _ret == FSCRYPT_KEY_STATUS_ABSENT);
+
+	// Without the key, the names are shown as no-key names.
+	TEST_RES(count_entries(ENC_DIR, "secret.txt", &found),
+		 _ret == 2 && !found);
+	TEST_ERRNO(open(ENC_FILE, O_RDONLY), ENOENT);
+	TEST_ERRNO(open(ENC_DIR "/new_file", O_CREAT | O_WRONLY, 0644),
+		   ENOKEY);
+	TEST_ERRNO(mkdir(ENC_DIR "/new_dir", 0755), ENOKEY);
+
+	// The contents are decrypted again after the key is added back.
+	TEST_SUCC(add_key());
+	fd = TEST_SUCC(open(ENC_FILE, O_RDONLY));
+	TEST_RES(read(fd, buf, sizeof(buf)),
+		 _ret == sizeof(buf) && memcmp(buf, FILE_CONTENT, _ret) == 0);
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(unlink(ENC_FILE));
+	CHECK(rmdir(ENC_SUBDIR));
+	CHECK(rmdir(ENC_DIR));
+	CHECK(unlink(PLAIN_FILE));
+	CHECK(rmdir(PLAIN_DIR));
+	CHECK(close(root_fd));
+}
+END_SETUP()
@@ -0,0 +1,227 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/types.h>
+#include <sys/wait.h>
+#include <sys/xattr.h>
+#include <unistd.h>
+
+#include "../../common/test.h"
+
+#define ACL_DIR "/ext2/test_acl_dir"
+#define ACL_FILE "/ext2/test_acl_dir/file"
+#define ACL_INHERITED_FILE "/ext2/test_acl_dir/inherited_file"
+#define ACL_INHERITED_DIR "/ext2/test_acl_dir/inherited_dir"
+
+#define XATTR_ACL_ACCESS "system.posix_acl_access"
+#define XATTR_ACL_DEFAULT "system.posix_acl_default"
+
+#define ACL_VERSION 0x0002
+#define ACL_USER_OBJ 0x01
+#define ACL_USER 0x02
+#define ACL_GROUP_OBJ 0x04
+#define ACL_MASK 0x10
+#define ACL_OTHER 0x20
+#define ACL_UNDEFINED_ID ((uint32_t)-1)
+
+#define TEST_UID 1000
+
+struct acl_entry {
+	uint16_t tag;
+	uint16_t perm;
+	uint32_t id;
+};
+
+struct acl {
+	uint32_t version;
+	struct acl_entry entries[5];
+};
+
+static const struct acl named_user_acl = {
+	.version = ACL_VERSION,
+	.entries = {
+		{ ACL_USER_OBJ, 6, ACL_UNDEFINED_ID },
+		{ ACL_USER, 4, TEST_UID },
+		{ ACL_GROUP_OBJ, 0, ACL_UNDEFINED_ID },
+		{ ACL_MASK, 4, ACL_UNDEFINED_ID },
+		{ ACL_OTHER, 0, ACL_UNDEFINED_ID },
+	},
+};
+
+static const struct acl default_acl = {
+	.version = ACL_VERSION,
+	.entries = {
+		{ ACL_USER_OBJ, 7, ACL_UNDEFINED_ID },
+		{ ACL_USER, 7, TEST_UID },
+		{ ACL_GROUP_OBJ, 5, ACL_UNDEFINED_ID },
+		{ ACL_MASK, 7, ACL_UNDEFINED_ID },
+		{ ACL_OTHER, 0, ACL_UNDEFINED_ID },
+	},
+};
+
+#define MINIMAL_ACL_SIZE (sizeof(uint32_t) + 3 * sizeof(struct acl_entry))
+
+static const struct acl minimal_acl = {
+	.version = ACL_VERSION,
+	.entries = {
+		{ ACL_USER_OBJ, 7, ACL_UNDEFINED_ID },
+		{ ACL_GROUP_OBJ, 5, ACL_UNDEFINED_ID },
+		{ ACL_OTHER, 1, ACL_UNDEFINED_ID },
+	},
+};
+
+static const struct acl unsorted_acl = {
+	.version = ACL_VERSION,
+	.entries = {
+		{ ACL_GROUP_OBJ, 5, ACL_UNDEFINED_ID },
+		{ ACL_USER_OBJ, 7, ACL_UNDEFINED_ID },
+		{ ACL_OTHER, 1, ACL_UNDEFINED_ID },
+	},
+};
+
+#define WAIT_AND_CHECK_CHILD(pid)                            \
+	do {                                                 \
+		int status;                                  \
+		TEST_RES(waitpid(pid, &status, 0),           \
+			 _ret == pid && WIFEXITED(status) && \
+				 WEXITSTATUS(status) == 0);  \
+	} while (0)
+
+FN_SETUP(create_acl_dir)
+{
+	umask(0);
+	CHECK(mkdir(ACL_DIR, 0755));
+	CHECK(close(CHECK(open(ACL_FILE, O_CREAT | O_WRONLY, 0600))));
+}
+END_SETUP()
+
+FN_TEST(set_and_get_access_acl)
+{
+	struct acl acl;
+	struct stat stat_buf;
+
+	TEST_SUCC(setxattr(ACL_FILE, XATTR_ACL_ACCESS, &named_user_acl,
+			   sizeof(named_user_acl), 0));
+	TEST_RES(getxattr(ACL_FILE, XATTR_ACL_ACCESS, NULL, 0),
+		 _ret == sizeof(named_user_acl));
+	TEST_RES(getxattr(ACL_FILE, XATTR_ACL_ACCESS, &acl, sizeof(acl)),
+		 _ret == sizeof(named_user_acl) &&
+			 memcmp(&acl, &named_user_acl, sizeof(acl)) == 0);
+
+	// The group permission bits reflect the mask entry.
+	TEST_RES(stat(ACL_FILE, &stat_buf), (stat_buf.st_mode & 0777) == 0640);
+}
+END_TEST()
+
+FN_TEST(access_acl_grants_named_user)
+{
+	pid_t pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		CHECK(setuid(TEST_UID));
+		CHECK(close(CHECK(open(ACL_FILE, O_RDONLY))));
+		CHECK_WITH(open(ACL_FILE, O_WRONLY), _ret < 0 && errno == EACCES);
+		exit(EXIT_SUCCESS);
+	} else {
+		WAIT_AND_CHECK_CHILD(pid);
+	}
+}
+END_TEST()
+
+FN_TEST(chmod_updates_access_acl)
+{
+	struct acl acl;
+
+	TEST_SUCC(chmod(ACL_FILE, 0600));
+	TEST_RES(getxattr(ACL_FILE, XATTR_ACL_ACCESS, &acl, sizeof(acl)),
+		 _ret == sizeof(named_user_acl) && acl.entries[1].perm == 4 &&
+			 acl.entries[3].perm == 0);
+}
+END_TEST()
+
+FN_TEST(minimal_access_acl_is_not_stored)
+{
+	struct stat stat_buf;
+
+	TEST_SUCC(setxattr(ACL_FILE, XATTR_ACL_ACCESS, &minimal_acl,
+			   MINIMAL_ACL_SIZE, 0));
+	TEST_RES(stat(ACL_FILE, &stat_buf), (stat_buf.st_mode & 0777) == 0751);
+	TEST_ERRNO(getxattr(ACL_FILE, XATTR_ACL_ACCESS, NULL, 0), ENODATA);
+}
+END_TEST()
+
+FN_TEST(invalid_acls)
+{
+	TEST_ERRNO(setxattr(ACL_FILE, XATTR_ACL_ACCESS, &unsorted_acl,
+			    MINIMAL_ACL_SIZE, 0),
+		   EINVAL);
+	TEST_ERRNO(setxattr(ACL_FILE, XATTR_ACL_ACCESS, &minimal_acl,
+			    MINIMAL_ACL_SIZE - 1, 0),
+		   EINVAL);
+	TEST_ERRNO(setxattr(ACL_FILE, XATTR_ACL_DEFAULT, &minimal_acl,
+			    MINIMAL_ACL_SIZE, 0),
+		   EACCES);
+}
+END_TEST()
+
+FN_TEST(default_acl_is_inherited)
+{
+	struct acl acl;
+	struct stat stat_buf;
+
+	TEST_SUCC(setxattr(ACL_DIR, XATTR_ACL_DEFAULT, &default_acl,
+			   sizeof(default_acl), 0));
+
+	TEST_SUCC(close(TEST_SUCC(
+		open(ACL_INHERITED_FILE, O_CREAT | O_WRONLY, 0666))));
+	TEST_RES(stat(ACL_INHERITED_FILE, &stat_buf),
+		 (stat_buf.st_mode & 0777) == 0660);
+	TEST_RES(getxattr(ACL_INHERITED_FILE, XATTR_ACL_ACCESS, &acl,
+			  sizeof(acl)),
+		 _ret == sizeof(default_acl) && acl.entries[0].perm == 6 &&
+			 acl.entries[1].perm == 7 && acl.entries[3].perm == 6);
+	TEST_ERRNO(getxattr(ACL_INHERITED_FILE, XATTR_ACL_DEFAULT, NULL, 0),
+		   ENODATA);
+
+	TEST_SUCC(mkdir(ACL_INHERITED_DIR, 0777));
+	TEST_RES(stat(ACL_INHERITED_DIR, &stat_buf),
+		 (stat_buf.st_mode & 0777) == 0770);
+	TEST_RES(getxattr(ACL_INHERITED_DIR, XATTR_ACL_DEFAULT, &acl,
+			  sizeof(acl)),
+		 _ret == sizeof(default_acl) &&
+			 memcmp(&acl, &default_acl, sizeof(acl)) == 0);
+
+	TEST_SUCC(removexattr(ACL_DIR, XATTR_ACL_DEFAULT));
+	TEST_ERRNO(removexattr(ACL_DIR, XATTR_ACL_DEFAULT), ENODATA);
+}
+END_TEST()
+
+FN_TEST(only_owner_can_set_acls)
+{
+	pid_t pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		CHECK(setuid(TEST_UID));
+		CHECK_WITH(setxattr(ACL_FILE, XATTR_ACL_ACCESS, &minimal_acl,
+				    MINIMAL_ACL_SIZE, 0),
+			   _ret < 0 && errno == EPERM);
+		exit(EXIT_SUCCESS);
+	} else {
+		WAIT_AND_CHECK_CHILD(pid);
+	}
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(unlink(ACL_FILE));
+	CHECK(unlink(ACL_INHERITED_FILE));
+	CHECK(rmdir(ACL_INHERITED_DIR));
+	CHECK(rmdir(ACL_DIR));
+}
+END_SETUP()
@@ -0,0 +1,286 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdint.h>
+#include <string.h>
+#include <sys/quota.h>
+#include <sys/syscall.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "../../common/test.h"
+
+#define EXT2_DEV "/dev/vda"
+#define QUOTA_DIR "/ext2/test_quota_dir"
+#define QUOTA_FILE "/ext2/test_quota_dir/aquota.user"
+#define USER_FILE(n) QUOTA_DIR "/user_file" #n
+
+#define TEST_UID 1000
+
+#ifndef QFMT_VFS_V1
+#define QFMT_VFS_V1 4
+#endif
+
+#ifndef Q_GETNEXTQUOTA
+#define Q_GETNEXTQUOTA 0x800009
+#endif
+
+struct next_dqblk {
+	uint64_t dqb_bhardlimit;
+	uint64_t dqb_bsoftlimit;
+	uint64_t dqb_curspace;
+	uint64_t dqb_ihardlimit;
+	uint64_t dqb_isoftlimit;
+	uint64_t dqb_curinodes;
+	uint64_t dqb_btime;
+	uint64_t dqb_itime;
+	uint32_t dqb_valid;
+	uint32_t dqb_id;
+};
+
+static int do_quotactl(int cmd, int type, int id, void *addr)
+{
+	return quotactl(QCMD(cmd, type), EXT2_DEV, id, addr);
+}
+
+// Creates an empty quota file in the `vfsv1` format.
+static void create_quota_file(void)
+{
+	uint32_t block[512] = { 0 };
+
+	// The header
+	block[0] = 0xd9c01f11;
+	block[1] = 1;
+	// The grace times
+	block[2] = 7 * 24 * 3600;
+	block[3] = 7 * 24 * 3600;
+	// The number of blocks, which are the header and the root of the tree
+	block[5] = 2;
+
+	int fd = CHECK(open(QUOTA_FILE, O_CREAT | O_WRONLY | O_TRUNC, 0600));
+	CHECK_WITH(write(fd, block, sizeof(block)), _ret == sizeof(block));
+	CHECK(close(fd));
+}
+
+static int run_as_user(int (*fn)(void))
+{
+	pid_t child = CHECK(fork());
+	if (child == 0) {
+		if (setuid(TEST_UID) < 0)
+			_exit(-1);
+		_exit(fn());
+	}
+
+	int status = 0;
+	CHECK(waitpid(child, &status, 0));
+	if (!WIFEXITED(status)) {
+		errno = ECHILD;
+		return -1;
+	}
+
+	return WEXITSTATUS(status);
+}
+
+FN_SETUP(create)
+{
+	CHECK(mkdir(QUOTA_DIR, 0777));
+	CHECK(chmod(QUOTA_DIR, 0777));
+	create_quota_file();
+}
+END_SETUP()
+
+FN_TEST(invalid_targets)
+{
+	struct dqblk dq;
+
+	TEST_ERRNO(quotactl(QCMD(Q_GETQUOTA, USRQUOTA), "/ext2", 0, (void *)&dq),
+		   ENOTBLK);
+	TEST_ERRNO(do_quotactl(Q_GETFMT, 5, 0, &dq), EINVAL);
+
+	int fd = TEST_SUCC(open("/", O_RDONLY | O_DIRECTORY));
+	TEST_ERRNO(syscall(SYS_quotactl_fd, fd, QCMD(Q_GETQUOTA, USRQUOTA), 0,
+			   &dq),
+		   ENOSYS);
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(quota_off)
+{
+	uint32_t fmt;
+	struct dqblk dq;
+
+	TEST_ERRNO(do_quotactl(Q_GETFMT, USRQUOTA, 0, &fmt), ESRCH);
+	TEST_ERRNO(do_quotactl(Q_GETQUOTA, USRQUOTA, TEST_UID, &dq), ESRCH);
+	TEST_SUCC(do_quotactl(Q_QUOTAOFF, USRQUOTA, 0, NULL));
+}
+END_TEST()
+
+FN_TEST(quota_on)
+{
+	uint32_t fmt;
+
+	TEST_ERRNO(do_quotactl(Q_QUOTAON, USRQUOTA, 100, QUOTA_FILE), ESRCH);
+	TEST_ERRNO(do_quotactl(Q_QUOTAON, USRQUOTA, QFMT_VFS_V1, "/tmp"),
+		   EXDEV);
+
+	TEST_SUCC(do_quotactl(Q_QUOTAON, USRQUOTA, QFMT_VFS_V1, QUOTA_FILE));
+	TEST_ERRNO(do_quotactl(Q_QUOTAON, USRQUOTA, QFMT_VFS_V1, QUOTA_FILE),
+		   EBUSY);
+	TEST_RES(do_quotactl(Q_GETFMT, USRQUOTA, 0, &fmt),
+		 fmt == QFMT_VFS_V1);
+}
+END_TEST()
+
+FN_TEST(info)
+{
+	struct dqinfo info;
+
+	TEST_RES(do_quotactl(Q_GETINFO, USRQUOTA, 0, &info),
+		 info.dqi_bgrace == 7 * 24 * 3600 &&
+			 info.dqi_igrace == 7 * 24 * 3600);
+
+	info.dqi_bgrace = 3600;
+	info.dqi_valid = IIF_BGRACE;
+	TEST_SUCC(do_quotactl(Q_SETINFO, USRQUOTA, 0, &info));
+	TEST_RES(do_quotactl(Q_GETINFO, USRQUOTA, 0, &info),
+		 info.dqi_bgrace == 3600 && info.dqi_igrace == 7 * 24 * 3600);
+
+	info.dqi_flags = 0x100;
+	info.dqi_valid = IIF_FLAGS;
+	TEST_ERRNO(do_quotactl(Q_SETINFO, USRQUOTA, 0, &info), EINVAL);
+}
+END_TEST()
+
+FN_TEST(set_quota)
+{
+	struct dqblk dq = { 0 };
+
+	// Two blocks of 4 KiB and two inodes
+	dq.dqb_bhardlimit = 8;
+	dq.dqb_ihardlimit = 2;
+	dq.dqb_valid = QIF_LIMITS;
+	TEST_SUCC(do_quotactl(Q_SETQUOTA, USRQUOTA, TEST_UID, &dq));
+
+	memset(&dq, 0, sizeof(dq));
+	TEST_RES(do_quotactl(Q_GETQUOTA, USRQUOTA, TEST_UID, &dq),
+		 dq.dqb_bhardlimit == 8 && dq.dqb_ihardlimit == 2 &&
+			 dq.dqb_curspace == 0 && dq.dqb_curinodes == 0);
+}
+END_TEST()
+
+static int exceed_limits(void)
+{
+	char buf[4096] = { 0 };
+
+	int fd = open(USER_FILE(1), O_CREAT | O_WRONLY, 0600);
+	if (fd < 0)
+		return errno;
+	if (write(fd, buf, sizeof(buf)) != sizeof(buf))
+		return errno;
+	if (pwrite(fd, buf, sizeof(buf), 2 * sizeof(buf)) >= 0)
+		return 0;
+	if (errno != EDQUOT)
+		return errno;
+	close(fd);
+
+	fd = open(USER_FILE(2), O_CREAT | O_WRONLY, 0600);
+	if (fd < 0)
+		return errno;
+	close(fd);
+
+	if (open(USER_FILE(3), O_CREAT | O_WRONLY, 0600) >= 0)
+		return 0;
+	if (errno != EDQUOT)
+		return errno;
+
+	return EDQUOT;
+}
+
+FN_TEST(enforce_limits)
+{
+	struct dqblk dq;
+
+	TEST_RES(run_as_user(exceed_limits), _ret == EDQUOT);
+	TEST_RES(do_quotactl(Q_GETQUOTA, USRQUOTA, TEST_UID, &dq),
+		 dq.dqb_curspace == 4096 && dq.dqb_curinodes == 2);
+}
+END_TEST()
+
+static int get_quotas(void)
+{
+	struct dqblk dq;
+
+	if (do_quotactl(Q_GETQUOTA, USRQUOTA, TEST_UID, &dq) < 0)
+		return errno;
+	if (do_quotactl(Q_GETQUOTA, USRQUOTA, 0, &dq) >= 0)
+		return 0;
+
+	return errno;
+}
+
+FN_TEST(permission)
+{
+	TEST_RES(run_as_user(get_quotas), _ret == EPERM);
+}
+END_TEST()
+
+FN_TEST(next_quota)
+{
+	struct next_dqblk dq;
+
+	TEST_RES(do_quotactl(Q_GETNEXTQUOTA, USRQUOTA, 1, &dq),
+		 dq.dqb_id == TEST_UID && dq.dqb_curinodes == 2);
+	TEST_ERRNO(do_quotactl(Q_GETNEXTQUOTA, USRQUOTA, TEST_UID + 1, &dq),
+		   ENOENT);
+}
+END_TEST()
+
+FN_TEST(chown_transfers_usage)
+{
+	struct dqblk dq;
+
+	TEST_SUCC(chown(USER_FILE(2), 0, 0));
+	TEST_RES(do_quotactl(Q_GETQUOTA, USRQUOTA, TEST_UID, &dq),
+		 dq.dqb_curinodes == 1);
+	TEST_SUCC(chown(USER_FILE(2), TEST_UID, 0));
+	TEST_RES(do_quotactl(Q_GETQUOTA, USRQUOTA, TEST_UID, &dq),
+		 dq.dqb_curinodes == 2);
+}
+END_TEST()
+
+FN_TEST(quota_off_after_on)
+{
+	uint32_t fmt;
+
+	TEST_SUCC(do_quotactl(Q_SYNC, USRQUOTA, 0, NULL));
+	TEST_SUCC(quotactl(QCMD(Q_SYNC, USRQUOTA), NULL, 0, NULL));
+	TEST_SUCC(do_quotactl(Q_QUOTAOFF, USRQUOTA, 0, NULL));
+	TEST_ERRNO(do_quotactl(Q_GETFMT, USRQUOTA, 0, &fmt), ESRCH);
+}
+END_TEST()
+
+FN_TEST(reload_quota_file)
+{
+	struct dqblk dq;
+
+	// The usage and limits are written back to the quota file on turning off quotas.
+	TEST_SUCC(do_quotactl(Q_QUOTAON, USRQUOTA, QFMT_VFS_V1, QUOTA_FILE));
+	TEST_RES(do_quotactl(Q_GETQUOTA, USRQUOTA, TEST_UID, &dq),
+		 dq.dqb_bhardlimit == 8 && dq.dqb_ihardlimit == 2 &&
+			 dq.dqb_curspace == 4096 && dq.dqb_curinodes == 2);
+	TEST_SUCC(do_quotactl(Q_QUOTAOFF, USRQUOTA, 0, NULL));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(unlink(USER_FILE(1)));
+	CHECK(unlink(USER_FILE(2)));
+	CHECK(unlink(QUOTA_FILE));
+	CHECK(rmdir(QUOTA_DIR));
+}
+END_SETUP()
@@ -0,0 +1,326 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <dirent.h>
+#include <fcntl.h>
+#include <linux/fuse.h>
+#include <signal.h>
+#include <stddef.h>
+#include <sys/mount.h>
+#include <sys/stat.h>
+#include <sys/vfs.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "../../common/test.h"
+
+#define MNT_DIR "/tmp/fuse_basic"
+#define FUSE_SUPER_MAGIC 0x65735546
+
+#define ROOT_INO FUSE_ROOT_ID
+#define FILE_INO 2
+#define FILE_NAME "hello"
+
+static const char initial_content[] = "Hello, FUSE!\n";
+
+static int fuse_fd;
+static pid_t daemon_pid;
+
+static char file_data[4096];
+static size_t file_size;
+
+static char req_buf[FUSE_MIN_READ_BUFFER * 16];
+static char reply_buf[8192];
+
+static void reply(const struct fuse_in_header *in, int error,
+		  const void *data, size_t len)
+{
+	struct fuse_out_header *out = (struct fuse_out_header *)reply_buf;
+
+	out->len = sizeof(*out) + len;
+	out->error = error;
+	out->unique = in->unique;
+	memcpy(reply_buf + sizeof(*out), data, len);
+
+	if (write(fuse_fd, reply_buf, out->len) != (ssize_t)out->len)
+		exit(EXIT_FAILURE);
+}
+
+static void fill_attr(uint64_t ino, struct fuse_attr *attr)
+{
+	memset(attr, 0, sizeof(*attr));
+	attr->ino = ino;
+	if (ino == ROOT_INO) {
+		attr->mode = S_IFDIR | 0755;
+		attr->nlink = 2;
+	} else {
+		attr->mode = S_IFREG | 0644;
+		attr->nlink = 1;
+		attr->size = file_size;
+	}
+}
+
+static void do_readdir(const struct fuse_in_header *in,
+		       const struct fuse_read_in *read_in)
+{
+	static const struct {
+		const char *name;
+		uint64_t ino;
+		uint32_t type;
+	} entries[] = {
+		{ ".", ROOT_INO, DT_DIR },
+		{ "..", ROOT_INO, DT_DIR },
+		{ FILE_NAME, FILE_INO, DT_REG },
+	};
+	char buf[512];
+	size_t len = 0;
+	uint64_t i;
+
+	for (i = read_in->offset; i < sizeof(entries) / sizeof(entries[0]);
+	     ++i) {
+		struct fuse_dirent *dirent = (struct fuse_dirent *)(buf + len);
+		size_t namelen = strlen(entries[i].name);
+
+		dirent->ino = entries[i].ino;
+		dirent->off = i + 1;
+		dirent->namelen = namelen;
+		dirent->type = entries[i].type;
+		memset(dirent->name, 0, FUSE_DIRENT_ALIGN(namelen));
+		memcpy(dirent->name, entries[i].name, namelen);
+		len += FUSE_DIRENT_SIZE(dirent);
+	}
+
+	reply(in, 0, buf, len);
+}
+
+static void serve(void)
+{
+	for (;;) {
+		struct fuse_in_header *in = (struct fuse_in_header *)req_buf;
+		void *arg = req_buf + sizeof(*in);
+		ssize_t len;
+
+		len = read(fuse_fd, req_buf, sizeof(req_buf));
+		if (len < 0 && errno == ENODEV)
+			exit(EXIT_SUCCESS);
+		if (len < (ssize_t)sizeof(*in))
+			exit(EXIT_FAILURE);
+
+		switch (in->opcode) {
+		case FUSE_INIT: {
+			struct fuse_init_out out = {
+				.major = FUSE_KERNEL_VERSION,
+				.minor = 31,
+				.max_write = sizeof(file_data),
+				.flags = FUSE_BIG_WRITES,
+			};
+			reply(in, 0, &out, sizeof(out));
+			break;
+		}
+		case FUSE_LOOKUP: {
+			struct fuse_entry_out out = { 0 };
+
+			if (in->nodeid != ROOT_INO ||
+			    strcmp((char *)arg, FILE_NAME) != 0) {
+				reply(in, -ENOENT, NULL, 0);
+				break;
+			}
+			out.nodeid = FILE_INO;
+			fill_attr(FILE_INO, &out.attr);
+			reply(in, 0, &out, sizeof(out));
+			break;
+		}
+		case FUSE_GETATTR:
+		case FUSE_SETATTR: {
+			struct fuse_attr_out out = { 0 };
+
+			if (in->opcode == FUSE_SETATTR) {
+				struct fuse_setattr_in *setattr_in = arg;
+
+				if ((setattr_in->valid & FATTR_SIZE) &&
+				    setattr_in->size <= sizeof(file_data))
+					file_size = setattr_in->size;
+			}
+			fill_attr(in->nodeid, &out.attr);
+			reply(in, 0, &out, sizeof(out));
+			break;
+		}
+		case FUSE_OPEN:
+		case FUSE_OPENDIR: {
+			struct fuse_open_out out = { .fh = 42 };
+
+			reply(in, 0, &out, sizeof(out));
+			break;
+		}
+		case FUSE_READ: {
+			struct fuse_read_in *read_in = arg;
+			size_t start = read_in->offset < file_size ?
+					       read_in->offset :
+					       file_size;
+			size_t end = start + read_in->size < file_size ?
+					     start + read_in->size :
+					     file_size;
+
+			reply(in, read_in->fh == 42 ? 0 : -EBADF,
+			      file_data + start, end - start);
+			break;
+		}
+		case FUSE_WRITE: {
+			struct fuse_write_in *write_in = arg;
+			struct fuse_write_out out = { .size = write_in->size };
+
+			if (write_in->offset + write_in->size >
+			    sizeof(file_data)) {
+				reply(in, -EFBIG, NULL, 0);
+				break;
+			}
+			memcpy(file_data + write_in->offset, write_in + 1,
+			       write_in->size);
+			if (write_in->offset + write_in->size > file_size)
+				file_size = write_in->offset + write_in->size;
+			reply(in, 0, &out, sizeof(out));
+			break;
+		}
+		case FUSE_READDIR:
+			do_readdir(in, arg);
+			break;
+		case FUSE_STATFS: {
+			struct fuse_statfs_out out = { 0 };
+
+			out.st.bsize = 4096;
+			out.st.frsize = 4096;
+			out.st.blocks = 100;
+			out.st.bfree = 50;
+			out.st.namelen = 255;
+			reply(in, 0, &out, sizeof(out));
+			break;
+		}
+		case FUSE_FLUSH:
+		case FUSE_RELEASE:
+		case FUSE_RELEASEDIR:
+			reply(in, 0, NULL, 0);
+			break;
+		case FUSE_FORGET:
+			break;
+		default:
+			reply(in, -ENOSYS, NULL, 0);
+			break;
+		}
+	}
+}
+
+FN_SETUP(mount)
+{
+	char options[128];
+
+	memcpy(file_data, initial_content, sizeof(initial_content) - 1);
+	file_size = sizeof(initial_content) - 1;
+
+	CHECK(mkdir(MNT_DIR, 0755));
+	fuse_fd = CHECK(open("/dev/fuse", O_RDWR));
+
+	snprintf(options, sizeof(options),
+		 "fd=%d,rootmode=40000,user_id=0,group_id=0", fuse_fd);
+	CHECK(mount("fuse_basic", MNT_DIR, "fuse.basic", 0, options));
+
+	daemon_pid = CHECK(fork());
+	if (daemon_pid == 0)
+		serve();
+}
+END_SETUP()
+
+FN_TEST(dev_fuse_read_requires_large_buffer)
+{
+	char buf[64];
+
+	TEST_ERRNO(read(fuse_fd, buf, sizeof(buf)), EINVAL);
+}
+END_TEST()
+
+FN_TEST(stat)
+{
+	struct stat st;
+	struct statfs sfs;
+
+	TEST_RES(stat(MNT_DIR, &st), S_ISDIR(st.st_mode) && st.st_ino == 1);
+	TEST_RES(stat(MNT_DIR "/" FILE_NAME, &st),
+		 S_ISREG(st.st_mode) && st.st_ino == FILE_INO &&
+			 st.st_size == sizeof(initial_content) - 1);
+	TEST_ERRNO(stat(MNT_DIR "/nonexistent", &st), ENOENT);
+
+	TEST_RES(statfs(MNT_DIR, &sfs),
+		 sfs.f_type == FUSE_SUPER_MAGIC && sfs.f_blocks == 100 &&
+			 sfs.f_bfree == 50);
+}
+END_TEST()
+
+FN_TEST(read_write)
+{
+	char buf[64];
+	int fd;
+
+	fd = TEST_SUCC(open(MNT_DIR "/" FILE_NAME, O_RDWR));
+	TEST_RES(read(fd, buf, sizeof(buf)),
+		 _ret == sizeof(initial_content) - 1 &&
+			 memcmp(buf, initial_content, _ret) == 0);
+
+	TEST_RES(pwrite(fd, "fuse", 4, 7), _ret == 4);
+	TEST_RES(pread(fd, buf, sizeof(buf), 0),
+		 _ret == sizeof(initial_content) - 1 &&
+			 memcmp(buf, "Hello, fuse!\n", _ret) == 0);
+
+	TEST_SUCC(ftruncate(fd, 5));
+	TEST_RES(pread(fd, buf, sizeof(buf), 0),
+		 _ret == 5 && memcmp(buf, "Hello", 5) == 0);
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(readdir)
+{
+	DIR *dir;
+	struct dirent *dirent;
+	int nr_entries = 0, found = 0;
+
+	dir = opendir(MNT_DIR);
+	TEST_RES(dir != NULL ? 0 : -1, dir != NULL);
+	if (dir == NULL)
+		return;
+
+	while ((dirent = readdir(dir)) != NULL) {
+		++nr_entries;
+		if (strcmp(dirent->d_name, FILE_NAME) == 0)
+			found = dirent->d_ino == FILE_INO &&
+				dirent->d_type == DT_REG;
+	}
+	TEST_RES(closedir(dir), nr_entries == 3 && found);
+}
+END_TEST()
+
+FN_TEST(unsupported_operation)
+{
+	TEST_ERRNO(mkdir(MNT_DIR "/dir", 0755), ENOSYS);
+}
+END_TEST()
+
+FN_TEST(abort)
+{
+	struct stat st;
+	int status;
+
+	// Closing the device file aborts the connection.
+	TEST_SUCC(kill(daemon_pid, SIGKILL));
+	TEST_RES(waitpid(daemon_pid, &status, 0), _ret == daemon_pid);
+	TEST_SUCC(close(fuse_fd));
+
+	TEST_ERRNO(stat(MNT_DIR "/" FILE_NAME, &st), ENOTCONN);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(umount(MNT_DIR));
+	CHECK(rmdir(MNT_DIR));
+}
+END_SETUP()
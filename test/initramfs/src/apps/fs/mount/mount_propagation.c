@@ -0,0 +1,139 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <errno.h>
+#include <fcntl.h>
+#include <sched.h>
+#include <sys/mount.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#include "../../common/test.h"
+
+#define PROP_ROOT "/tmp/prop_root"
+#define PROP_A "/tmp/prop_root/a"
+#define PROP_B "/tmp/prop_root/b"
+#define PROP_C "/tmp/prop_root/c"
+
+static void ensure_dir(const char *path)
+{
+	CHECK_WITH(mkdir(path, 0755), _ret >= 0 || errno == EEXIST);
+}
+
+static int file_exists(const char *path)
+{
+	struct stat st;
+
+	return stat(path, &st) == 0;
+}
+
+static int create_file(const char *path)
+{
+	int fd = open(path, O_CREAT | O_WRONLY, 0644);
+	if (fd < 0)
+		return -1;
+	return close(fd);
+}
+
+FN_SETUP(mount_trees)
+{
+	CHECK(unshare(CLONE_NEWNS));
+
+	ensure_dir(PROP_ROOT);
+	CHECK(mount("tmpfs", PROP_ROOT, "tmpfs", 0, NULL));
+	ensure_dir(PROP_A);
+	ensure_dir(PROP_B);
+	ensure_dir(PROP_C);
+
+	CHECK(mount("tmpfs", PROP_A, "tmpfs", 0, NULL));
+	ensure_dir(PROP_A "/sub");
+	ensure_dir(PROP_A "/sub2");
+}
+END_SETUP()
+
+FN_TEST(invalid_propagation_flags)
+{
+	TEST_ERRNO(mount(NULL, PROP_A, NULL, MS_SHARED | MS_PRIVATE, NULL),
+		   EINVAL);
+	TEST_ERRNO(mount(NULL, PROP_A, NULL, MS_SLAVE | MS_RDONLY, NULL),
+		   EINVAL);
+	TEST_ERRNO(mount(NULL, PROP_A "/sub", NULL, MS_SHARED, NULL), EINVAL);
+}
+END_TEST()
+
+FN_TEST(shared_peers)
+{
+	TEST_SUCC(mount(NULL, PROP_A, NULL, MS_SHARED, NULL));
+	TEST_SUCC(mount(PROP_A, PROP_B, NULL, MS_BIND, NULL));
+
+	// Mount events propagate from `PROP_A` to its peer `PROP_B`.
+	TEST_SUCC(mount("tmpfs", PROP_A "/sub", "tmpfs", 0, NULL));
+	TEST_SUCC(create_file(PROP_A "/sub/file"));
+	TEST_RES(file_exists(PROP_B "/sub/file"), _ret == 1);
+
+	// Mount and unmount events propagate from `PROP_B` to its peer `PROP_A`.
+	TEST_SUCC(umount(PROP_B "/sub"));
+	TEST_RES(file_exists(PROP_A "/sub/file"), _ret == 0);
+
+	TEST_SUCC(mount("tmpfs", PROP_B "/sub", "tmpfs", 0, NULL));
+	TEST_SUCC(create_file(PROP_B "/sub/file"));
+	TEST_RES(file_exists(PROP_A "/sub/file"), _ret == 1);
+	TEST_SUCC(umount(PROP_A "/sub"));
+	TEST_RES(file_exists(PROP_B "/sub/file"), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(slave)
+{
+	TEST_SUCC(mount(NULL, PROP_B, NULL, MS_SLAVE, NULL));
+
+	// Mount events propagate from the master to the slave.
+	TEST_SUCC(mount("tmpfs", PROP_A "/sub", "tmpfs", 0, NULL));
+	TEST_SUCC(create_file(PROP_A "/sub/file"));
+	TEST_RES(file_exists(PROP_B "/sub/file"), _ret == 1);
+
+	// Mount events do not propagate from the slave to the master.
+	TEST_SUCC(mount("tmpfs", PROP_B "/sub2", "tmpfs", 0, NULL));
+	TEST_SUCC(create_file(PROP_B "/sub2/file"));
+	TEST_RES(file_exists(PROP_A "/sub2/file"), _ret == 0);
+	TEST_SUCC(umount(PROP_B "/sub2"));
+
+	// Unmount events propagate from the master to the slave.
+	TEST_SUCC(umount(PROP_A "/sub"));
+	TEST_RES(file_exists(PROP_B "/sub/file"), _ret == 0);
+
+	// Private mounts do not receive events.
+	TEST_SUCC(mount(NULL, PROP_B, NULL, MS_PRIVATE, NULL));
+	TEST_SUCC(mount("tmpfs", PROP_A "/sub", "tmpfs", 0, NULL));
+	TEST_SUCC(create_file(PROP_A "/sub/file"));
+	TEST_RES(file_exists(PROP_B "/sub/file"), _ret == 0);
+	TEST_SUCC(umount(PROP_A "/sub"));
+}
+END_TEST()
+
+FN_TEST(unbindable)
+{
+	TEST_SUCC(mount(NULL, PROP_B, NULL, MS_UNBINDABLE, NULL));
+	TEST_ERRNO(mount(PROP_B, PROP_C, NULL, MS_BIND, NULL), EINVAL);
+
+	// Recursive bind mounts skip unbindable mounts.
+	TEST_SUCC(mount(PROP_ROOT, PROP_C, NULL, MS_BIND | MS_REC, NULL));
+	TEST_RES(file_exists(PROP_C "/a/sub"), _ret == 1);
+	TEST_RES(file_exists(PROP_C "/b/sub"), _ret == 0);
+	TEST_SUCC(umount(PROP_C "/a"));
+	TEST_SUCC(umount(PROP_C));
+
+	TEST_SUCC(mount(NULL, PROP_B, NULL, MS_PRIVATE, NULL));
+	TEST_SUCC(mount(PROP_B, PROP_C, NULL, MS_BIND, NULL));
+	TEST_SUCC(umount(PROP_C));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(umount(PROP_B));
+	CHECK(umount(PROP_A));
+	CHECK(umount(PROP_ROOT));
+}
+END_SETUP()
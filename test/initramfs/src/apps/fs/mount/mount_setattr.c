@@ -0,0 +1,150 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <errno.h>
+#include <fcntl.h>
+#include <sched.h>
+#include <stdint.h>
+#include <sys/mount.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#include "../../common/test.h"
+
+#ifndef SYS_mount_setattr
+#define SYS_mount_setattr 442
+#endif
+
+#define ATTR_RDONLY 0x00000001
+#define ATTR_NOEXEC 0x00000008
+#define ATTR__ATIME 0x00000070
+#define ATTR_NOATIME 0x00000010
+#define ATTR_IDMAP 0x00100000
+#define ATTR_RECURSIVE 0x8000
+
+struct test_mount_attr {
+	uint64_t attr_set;
+	uint64_t attr_clr;
+	uint64_t propagation;
+	uint64_t userns_fd;
+};
+
+#define SETATTR_ROOT "/tmp/setattr_root"
+#define SETATTR_CHILD "/tmp/setattr_root/child"
+#define SETATTR_DIR "/tmp/setattr_root/dir"
+
+static int do_mount_setattr(const char *path, unsigned int flags,
+			    struct test_mount_attr *attr, size_t size)
+{
+	return syscall(SYS_mount_setattr, AT_FDCWD, path, flags, attr, size);
+}
+
+static int create_file(const char *path)
+{
+	int fd = open(path, O_CREAT | O_WRONLY, 0644);
+	if (fd < 0)
+		return -1;
+	close(fd);
+	return unlink(path);
+}
+
+static void ensure_dir(const char *path)
+{
+	CHECK_WITH(mkdir(path, 0755), _ret >= 0 || errno == EEXIST);
+}
+
+FN_SETUP(mount_trees)
+{
+	CHECK(unshare(CLONE_NEWNS));
+
+	ensure_dir(SETATTR_ROOT);
+	CHECK(mount("tmpfs", SETATTR_ROOT, "tmpfs", 0, NULL));
+	ensure_dir(SETATTR_CHILD);
+	ensure_dir(SETATTR_DIR);
+	CHECK(mount("tmpfs", SETATTR_CHILD, "tmpfs", 0, NULL));
+}
+END_SETUP()
+
+FN_TEST(invalid_arguments)
+{
+	struct test_mount_attr attr = { .attr_set = ATTR_RDONLY };
+
+	TEST_ERRNO(do_mount_setattr(SETATTR_ROOT, 0, &attr, 8), EINVAL);
+	TEST_ERRNO(do_mount_setattr(SETATTR_ROOT, 0x1, &attr, sizeof(attr)),
+		   EINVAL);
+	TEST_ERRNO(do_mount_setattr(SETATTR_DIR, 0, &attr, sizeof(attr)),
+		   EINVAL);
+
+	attr.attr_set = 1ULL << 40;
+	TEST_ERRNO(do_mount_setattr(SETATTR_ROOT, 0, &attr, sizeof(attr)),
+		   EINVAL);
+
+	// The atime flags must be cleared before being set.
+	attr.attr_set = ATTR_NOATIME;
+	TEST_ERRNO(do_mount_setattr(SETATTR_ROOT, 0, &attr, sizeof(attr)),
+		   EINVAL);
+	attr.attr_clr = ATTR__ATIME;
+	TEST_SUCC(do_mount_setattr(SETATTR_ROOT, 0, &attr, sizeof(attr)));
+}
+END_TEST()
+
+FN_TEST(rdonly_recursive)
+{
+	struct test_mount_attr attr = { .attr_set = ATTR_RDONLY };
+
+	TEST_SUCC(create_file(SETATTR_ROOT "/file"));
+	TEST_SUCC(create_file(SETATTR_CHILD "/file"));
+
+	TEST_SUCC(do_mount_setattr(SETATTR_ROOT, ATTR_RECURSIVE, &attr,
+				   sizeof(attr)));
+	TEST_ERRNO(create_file(SETATTR_ROOT "/file"), EROFS);
+	TEST_ERRNO(create_file(SETATTR_CHILD "/file"), EROFS);
+	TEST_ERRNO(mkdir(SETATTR_ROOT "/new_dir", 0755), EROFS);
+	TEST_ERRNO(access(SETATTR_ROOT, W_OK), EROFS);
+
+	// Clear the read-only attribute of the root mount only.
+	attr.attr_set = 0;
+	attr.attr_clr = ATTR_RDONLY;
+	TEST_SUCC(do_mount_setattr(SETATTR_ROOT, 0, &attr, sizeof(attr)));
+	TEST_SUCC(create_file(SETATTR_ROOT "/file"));
+	TEST_ERRNO(create_file(SETATTR_CHILD "/file"), EROFS);
+
+	TEST_SUCC(do_mount_setattr(SETATTR_ROOT, ATTR_RECURSIVE, &attr,
+				   sizeof(attr)));
+	TEST_SUCC(create_file(SETATTR_CHILD "/file"));
+}
+END_TEST()
+
+FN_TEST(idmap_with_init_user_ns)
+{
+	struct test_mount_attr attr = { .attr_set = ATTR_IDMAP };
+	int userns_fd = TEST_SUCC(open("/proc/self/ns/user", O_RDONLY));
+
+	// A file that is not a user namespace cannot be used.
+	attr.userns_fd = TEST_SUCC(open("/proc/self/ns/mnt", O_RDONLY));
+	TEST_ERRNO(do_mount_setattr(SETATTR_ROOT, 0, &attr, sizeof(attr)),
+		   EINVAL);
+	TEST_SUCC(close(attr.userns_fd));
+
+	// Idmapped mounts cannot use the initial user namespace.
+	attr.userns_fd = userns_fd;
+	TEST_ERRNO(do_mount_setattr(SETATTR_ROOT, 0, &attr, sizeof(attr)),
+		   EPERM);
+
+	attr.attr_set = 0;
+	attr.attr_clr = ATTR_IDMAP;
+	TEST_ERRNO(do_mount_setattr(SETATTR_ROOT, 0, &attr, sizeof(attr)),
+		   EINVAL);
+
+	TEST_SUCC(close(userns_fd));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(umount(SETATTR_CHILD));
+	CHECK(umount(SETATTR_ROOT));
+}
+END_SETUP()
@@ -0,0 +1,233 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <dirent.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+
+#include "../../common/test.h"
+
+#define CPU1_ONLINE "/sys/devices/system/cpu/cpu1/online"
+
+static char buf[4096];
+
+static ssize_t read_file(const char *path)
+{
+	int fd;
+	ssize_t len;
+
+	fd = open(path, O_RDONLY);
+	if (fd < 0)
+		return -1;
+
+	len = read(fd, buf, sizeof(buf) - 1);
+	close(fd);
+	if (len >= 0)
+		buf[len] = '\0';
+
+	return len;
+}
+
+static ssize_t write_file(const char *path, const char *value)
+{
+	int fd;
+	ssize_t len;
+
+	fd = open(path, O_WRONLY);
+	if (fd < 0)
+		return -1;
+
+	len = write(fd, value, strlen(value));
+	close(fd);
+
+	return len;
+}
+
+static char mask_path[64];
+static char list_path[64];
+
+static void set_irq_paths(int irq)
+{
+	snprintf(mask_path, sizeof(mask_path), "/proc/irq/%d/smp_affinity",
+		 irq);
+	snprintf(list_path, sizeof(list_path),
+		 "/proc/irq/%d/smp_affinity_list", irq);
+}
+
+// Returns the CPU that the IRQ is delivered to, or -1 on errors.
+static int read_affinity_list(void)
+{
+	if (read_file(list_path) < 0)
+		return -1;
+	return atoi(buf);
+}
+
+static long nr_cpus;
+static int writable_irq = -1;
+
+FN_SETUP(irqs)
+{
+	DIR *dir;
+	struct dirent *entry;
+	char value[16];
+	int irq, cpu, err;
+
+	nr_cpus = CHECK(sysconf(_SC_NPROCESSORS_CONF));
+
+	// Find an IRQ whose affinity can be changed by writing its current
+	// affinity back. The others are either managed by the kernel or
+	// cannot be redirected at all.
+	dir = CHECK_WITH(opendir("/proc/irq"), _ret != NULL);
+	while ((entry = readdir(dir)) != NULL) {
+		if (entry->d_name[0] < '0' || entry->d_name[0] > '9')
+			continue;
+
+		irq = atoi(entry->d_name);
+		set_irq_paths(irq);
+		cpu = CHECK(read_affinity_list());
+		snprintf(value, sizeof(value), "%d\n", cpu);
+		if (write_file(list_path, value) >= 0) {
+			writable_irq = irq;
+			break;
+		}
+		err = errno;
+		CHECK_WITH(err, _ret == EIO);
+	}
+	closedir(dir);
+}
+END_SETUP()
+
+FN_TEST(read_all)
+{
+	DIR *dir;
+	struct dirent *entry;
+	unsigned long mask;
+	int cpu;
+
+	dir = opendir("/proc/irq");
+	TEST_RES(dir, _ret != NULL);
+	if (dir == NULL)
+		return;
+
+	// The mask and the list must describe the same CPU.
+	while ((entry = readdir(dir)) != NULL) {
+		if (entry->d_name[0] < '0' || entry->d_name[0] > '9')
+			continue;
+
+		set_irq_paths(atoi(entry->d_name));
+		cpu = read_affinity_list();
+		TEST_RES(cpu, _ret >= 0 && _ret < nr_cpus);
+		TEST_RES(read_file(mask_path),
+			 _ret == nr_cpus / 4 + (nr_cpus % 4 != 0) + 1 &&
+				 buf[_ret - 1] == '\n');
+		mask = strtoul(buf, NULL, 16);
+		TEST_RES(mask, _ret == 1UL << cpu);
+	}
+	closedir(dir);
+}
+END_TEST()
+
+FN_TEST(write_valid)
+{
+	int last_cpu = nr_cpus - 1;
+	char value[16];
+
+	if (writable_irq < 0)
+		return;
+	set_irq_paths(writable_irq);
+
+	snprintf(value, sizeof(value), "%d\n", last_cpu);
+	TEST_RES(write_file(list_path, value), _ret == strlen(value));
+	TEST_RES(read_affinity_list(), _ret == last_cpu);
+
+	// Only one CPU can be targeted, so the lowest CPU in the mask wins.
+	TEST_RES(write_file(mask_path, "ff\n"), _ret == 3);
+	TEST_RES(read_affinity_list(), _ret == 0);
+
+	snprintf(value, sizeof(value), "%lx\n", 1UL << last_cpu);
+	TEST_RES(write_file(mask_path, value), _ret == strlen(value));
+	TEST_RES(read_affinity_list(), _ret == last_cpu);
+
+	TEST_RES(write_file(list_path, "0-1,3"), _ret == 5);
+	TEST_RES(read_affinity_list(), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(write_malformed)
+{
+	if (writable_irq < 0)
+		return;
+	set_irq_paths(writable_irq);
+
+	TEST_ERRNO(write_file(mask_path, "xyz"), EINVAL);
+	TEST_ERRNO(write_file(mask_path, "0"), EINVAL);
+	TEST_ERRNO(write_file(mask_path, "1,00000000,00000000"), EINVAL);
+	TEST_ERRNO(write_file(list_path, "a-b"), EINVAL);
+	TEST_ERRNO(write_file(list_path, ","), EINVAL);
+	TEST_ERRNO(write_file(list_path, "4096"), EINVAL);
+	TEST_RES(read_affinity_list(), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(write_offline_cpu)
+{
+	if (writable_irq < 0 || nr_cpus < 2)
+		return;
+	set_irq_paths(writable_irq);
+
+	TEST_RES(write_file(CPU1_ONLINE, "0"), _ret == 1);
+	TEST_ERRNO(write_file(list_path, "1"), EINVAL);
+	TEST_ERRNO(write_file(mask_path, "2"), EINVAL);
+	TEST_RES(read_affinity_list(), _ret == 0);
+	TEST_RES(write_file(CPU1_ONLINE, "1"), _ret == 1);
+
+	TEST_RES(write_file(list_path, "1"), _ret == 1);
+	TEST_RES(read_affinity_list(), _ret == 1);
+
+	// Taking the CPU offline redirects its interrupts to an online CPU.
+	TEST_RES(write_file(CPU1_ONLINE, "0"), _ret == 1);
+	TEST_RES(read_affinity_list(), _ret == 0);
+	TEST_RES(write_file(CPU1_ONLINE, "1"), _ret == 1);
+}
+END_TEST()
+
+FN_TEST(write_managed)
+{
+	DIR *dir;
+	struct dirent *entry;
+	char value[16];
+	int cpu, err;
+
+	dir = opendir("/proc/irq");
+	TEST_RES(dir, _ret != NULL);
+	if (dir == NULL)
+		return;
+
+	// The IRQs whose affinities are managed by the kernel (e.g., those of
+	// the virtio queues) or cannot be changed at all reject any changes
+	// with EIO and keep their affinities.
+	while ((entry = readdir(dir)) != NULL) {
+		if (entry->d_name[0] < '0' || entry->d_name[0] > '9')
+			continue;
+
+		set_irq_paths(atoi(entry->d_name));
+		cpu = read_affinity_list();
+		snprintf(value, sizeof(value), "%ld\n", (cpu + 1) % nr_cpus);
+		if (write_file(list_path, value) >= 0) {
+			// Restore the affinity of an unmanaged IRQ.
+			snprintf(value, sizeof(value), "%d\n", cpu);
+			TEST_RES(write_file(list_path, value),
+				 _ret == strlen(value));
+			continue;
+		}
+		err = errno;
+		TEST_RES(err, _ret == EIO);
+		TEST_RES(read_affinity_list(), _ret == cpu);
+	}
+	closedir(dir);
+}
+END_TEST()
@@ -0,0 +1,108 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <stdio.h>
+#include <string.h>
+#include <time.h>
+
+#include "../../common/test.h"
+
+static int read_task_schedstat(unsigned long long *runtime,
+			       unsigned long long *run_delay,
+			       unsigned long long *pcount)
+{
+	FILE *file;
+	int ret;
+
+	file = fopen("/proc/self/schedstat", "r");
+	if (file == NULL)
+		return -1;
+
+	ret = fscanf(file, "%llu %llu %llu", runtime, run_delay, pcount) == 3 ?
+		      0 :
+		      -1;
+	fclose(file);
+	return ret;
+}
+
+static int read_voluntary_switches(unsigned long long *switches)
+{
+	char line[256];
+	FILE *file;
+	int ret = -1;
+
+	file = fopen("/proc/self/status", "r");
+	if (file == NULL)
+		return -1;
+
+	while (fgets(line, sizeof(line), file) != NULL) {
+		if (sscanf(line, "voluntary_ctxt_switches: %llu", switches) ==
+		    1) {
+			ret = 0;
+			break;
+		}
+	}
+	fclose(file);
+	return ret;
+}
+
+FN_TEST(task_schedstat)
+{
+	unsigned long long runtime1, delay1, pcount1;
+	unsigned long long runtime2, delay2, pcount2;
+	volatile unsigned long i;
+
+	TEST_RES(read_task_schedstat(&runtime1, &delay1, &pcount1), _ret == 0);
+	for (i = 0; i < 10000000; i++)
+		;
+	TEST_RES(read_task_schedstat(&runtime2, &delay2, &pcount2), _ret == 0);
+
+	TEST_RES(0, pcount1 >= 1);
+	TEST_RES(0, runtime2 > runtime1);
+	TEST_RES(0, delay2 >= delay1 && pcount2 >= pcount1);
+}
+END_TEST()
+
+FN_TEST(voluntary_switches)
+{
+	struct timespec sleep_time = { .tv_sec = 0, .tv_nsec = 10000000 };
+	unsigned long long switches1, switches2;
+
+	TEST_RES(read_voluntary_switches(&switches1), _ret == 0);
+	TEST_SUCC(nanosleep(&sleep_time, NULL));
+	TEST_RES(read_voluntary_switches(&switches2), _ret == 0);
+
+	TEST_RES(0, switches2 > switches1);
+}
+END_TEST()
+
+FN_TEST(cpu_schedstat)
+{
+	unsigned long long fields[9];
+	char line[1024];
+	int version;
+	FILE *file;
+
+	file = CHECK_WITH(fopen("/proc/schedstat", "r"), _ret != NULL);
+
+	TEST_RES(fscanf(file, "version %d\n", &version), _ret == 1);
+	TEST_RES(version, _ret == 15);
+	TEST_RES(fscanf(file, "timestamp %*u\n"), _ret == 0);
+
+	TEST_RES(fgets(line, sizeof(line), file), _ret != NULL);
+	TEST_RES(sscanf(line,
+			"cpu%*d %llu %llu %llu %llu %llu %llu %llu %llu %llu",
+			&fields[0], &fields[1], &fields[2], &fields[3],
+			&fields[4], &fields[5], &fields[6], &fields[7],
+			&fields[8]),
+		 _ret == 9);
+	// sched_count >= sched_goidle
+	TEST_RES(0, fields[2] >= fields[3]);
+
+	TEST_RES(fgets(line, sizeof(line), file), _ret != NULL);
+	TEST_RES(strncmp(line, "domain0 ", 8), _ret == 0);
+
+	TEST_SUCC(fclose(file));
+}
+END_TEST()
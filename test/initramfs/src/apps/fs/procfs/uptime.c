@@ -0,0 +1,37 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <stdio.h>
+#include <time.h>
+
+#include "../../common/test.h"
+
+static int read_uptime(double *uptime, double *idle)
+{
+	FILE *file;
+	int ret;
+
+	file = fopen("/proc/uptime", "r");
+	if (file == NULL)
+		return -1;
+
+	ret = fscanf(file, "%lf %lf", uptime, idle) == 2 ? 0 : -1;
+	fclose(file);
+	return ret;
+}
+
+FN_TEST(idle_time_while_sleeping)
+{
+	struct timespec half_second = { .tv_sec = 0, .tv_nsec = 500000000 };
+	double uptime1, idle1, uptime2, idle2;
+
+	TEST_SUCC(read_uptime(&uptime1, &idle1));
+	TEST_SUCC(nanosleep(&half_second, NULL));
+	TEST_SUCC(read_uptime(&uptime2, &idle2));
+
+	TEST_RES(0, uptime2 - uptime1 >= 0.4 && uptime2 - uptime1 < 1.5);
+	// The CPU on which this process sleeps is idle, even if its tick is stopped.
+	TEST_RES(0, idle2 - idle1 >= 0.25);
+}
+END_TEST()
@@ -87,7 +87,10 @@ test_mount_bind_file() {
 
 echo "Start ext2 fs test......"
 test_ext2 "/ext2" "test_file.txt"
+./ext2/fscrypt
 ./ext2/mknod
+./ext2/posix_acl
+./ext2/quota
 ./ext2/rmdir
 ./ext2/unix_socket
 echo "All ext2 fs test passed."
@@ -100,6 +103,11 @@ echo "Start mount bind file test......"
 test_mount_bind_file
 echo "All mount bind file test passed."
 
+./debugfs/debugfs
+./tracefs/tracefs
+
+./fuse/fuse_basic
+
 ./inotify/inotify_align
 ./inotify/inotify_poll
 ./inotify/inotify_unlink
@@ -108,14 +116,24 @@ echo "All mount bind file test passed."
 ./isolation/pivot_root
 
 ./mount/mount_move
+./mount/mount_propagation
+./mount/mount_setattr
 
 ./overlayfs/ovl_test
 
 ./procfs/dentry_cache
+./procfs/irq_affinity
 ./procfs/pid_mem
+./procfs/schedstat
+./procfs/uptime
 
 ./pseudofs/memfd_access_err
 ./pseudofs/pseudo_dentry
 ./pseudofs/pseudo_dev_id
 ./pseudofs/pseudo_inode
 ./pseudofs/pseudo_mount
+
+./sysfs/cpu_hotplug
+./sysfs/sysfs_devices
+
+./tmpfs/tmpfs_limits
@@ -0,0 +1,126 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <fcntl.h>
+#include <sched.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+#include "../../common/test.h"
+
+#define CPU0_ONLINE "/sys/devices/system/cpu/cpu0/online"
+#define CPU1_ONLINE "/sys/devices/system/cpu/cpu1/online"
+#define CPUS_ONLINE "/sys/devices/system/cpu/online"
+#define CPUS_OFFLINE "/sys/devices/system/cpu/offline"
+
+static char buf[4096];
+
+static ssize_t read_attr(const char *path)
+{
+	int fd;
+	ssize_t len;
+
+	fd = open(path, O_RDONLY);
+	if (fd < 0)
+		return -1;
+
+	len = read(fd, buf, sizeof(buf) - 1);
+	close(fd);
+	if (len >= 0)
+		buf[len] = '\0';
+
+	return len;
+}
+
+static ssize_t write_attr(const char *path, const char *value)
+{
+	int fd;
+	ssize_t len;
+
+	fd = open(path, O_WRONLY);
+	if (fd < 0)
+		return -1;
+
+	len = write(fd, value, strlen(value));
+	close(fd);
+
+	return len;
+}
+
+static long nr_cpus;
+static cpu_set_t old_set;
+
+FN_SETUP(cpus)
+{
+	nr_cpus = CHECK(sysconf(_SC_NPROCESSORS_CONF));
+	CHECK(sched_getaffinity(0, sizeof(old_set), &old_set));
+}
+END_SETUP()
+
+FN_TEST(bsp_not_hotpluggable)
+{
+	TEST_RES(read_attr(CPU0_ONLINE), strcmp(buf, "1\n") == 0);
+	TEST_ERRNO(write_attr(CPU0_ONLINE, "0"), EIO);
+	TEST_RES(read_attr(CPU0_ONLINE), strcmp(buf, "1\n") == 0);
+}
+END_TEST()
+
+FN_TEST(offline_and_online)
+{
+	cpu_set_t set;
+	char expected[32];
+
+	if (nr_cpus < 2)
+		return;
+
+	if (nr_cpus == 2)
+		snprintf(expected, sizeof(expected), "0\n");
+	else if (nr_cpus == 3)
+		snprintf(expected, sizeof(expected), "0,2\n");
+	else
+		snprintf(expected, sizeof(expected), "0,2-%ld\n", nr_cpus - 1);
+
+	// Run on the CPU that is about to be taken offline, so that the
+	// current thread itself has to be migrated away.
+	CPU_ZERO(&set);
+	CPU_SET(1, &set);
+	TEST_SUCC(sched_setaffinity(0, sizeof(set), &set));
+	TEST_RES(sched_getcpu(), _ret == 1);
+
+	TEST_ERRNO(write_attr(CPU1_ONLINE, "2"), EINVAL);
+	TEST_RES(read_attr(CPU1_ONLINE), strcmp(buf, "1\n") == 0);
+
+	TEST_RES(write_attr(CPU1_ONLINE, "0"), _ret == 1);
+	TEST_RES(read_attr(CPU1_ONLINE), strcmp(buf, "0\n") == 0);
+	TEST_RES(read_attr(CPUS_OFFLINE), strcmp(buf, "1\n") == 0);
+	TEST_RES(read_attr(CPUS_ONLINE), strcmp(buf, expected) == 0);
+	// Taking an offline CPU offline again does nothing.
+	TEST_RES(write_attr(CPU1_ONLINE, "0"), _ret == 1);
+
+	// The affinity is broken since the only allowed CPU is offline.
+	TEST_RES(sched_getcpu(), _ret != 1);
+	TEST_RES(sched_getaffinity(0, sizeof(set), &set),
+		 !CPU_ISSET(1, &set) && CPU_ISSET(0, &set));
+	TEST_RES(sched_getaffinity(0, sizeof(set), &set),
+		 CPU_COUNT(&set) == nr_cpus - 1);
+
+	// An offline CPU cannot be the only allowed CPU.
+	CPU_ZERO(&set);
+	CPU_SET(1, &set);
+	TEST_ERRNO(sched_setaffinity(0, sizeof(set), &set), EINVAL);
+
+	TEST_RES(write_attr(CPU1_ONLINE, "1"), _ret == 1);
+	TEST_RES(read_attr(CPU1_ONLINE), strcmp(buf, "1\n") == 0);
+	TEST_RES(read_attr(CPUS_OFFLINE), strcmp(buf, "\n") == 0);
+
+	// Tasks can be placed on the CPU again.
+	TEST_SUCC(sched_setaffinity(0, sizeof(set), &set));
+	TEST_RES(sched_getcpu(), _ret == 1);
+	TEST_RES(sched_getaffinity(0, sizeof(set), &set),
+		 CPU_COUNT(&set) == 1 && CPU_ISSET(1, &set));
+
+	TEST_SUCC(sched_setaffinity(0, sizeof(old_set), &old_set));
+}
+END_TEST()
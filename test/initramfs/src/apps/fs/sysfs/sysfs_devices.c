@@ -0,0 +1,148 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <fcntl.h>
+#include <limits.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#include "../../common/test.h"
+
+static char buf[4096];
+
+static ssize_t read_attr(const char *path)
+{
+	int fd;
+	ssize_t len;
+
+	fd = open(path, O_RDONLY);
+	if (fd < 0)
+		return -1;
+
+	len = read(fd, buf, sizeof(buf) - 1);
+	close(fd);
+	if (len >= 0)
+		buf[len] = '\0';
+
+	return len;
+}
+
+static char link_buf[PATH_MAX];
+
+static ssize_t read_link(const char *path)
+{
+	ssize_t len;
+
+	len = readlink(path, link_buf, sizeof(link_buf) - 1);
+	if (len >= 0)
+		link_buf[len] = '\0';
+
+	return len;
+}
+
+FN_TEST(cpu)
+{
+	long nr_cpus = sysconf(_SC_NPROCESSORS_CONF);
+	char expected[32];
+
+	if (nr_cpus == 1)
+		snprintf(expected, sizeof(expected), "0\n");
+	else
+		snprintf(expected, sizeof(expected), "0-%ld\n", nr_cpus - 1);
+
+	TEST_RES(read_attr("/sys/devices/system/cpu/online"),
+		 strcmp(buf, expected) == 0);
+	TEST_RES(read_attr("/sys/devices/system/cpu/possible"),
+		 strcmp(buf, expected) == 0);
+	TEST_RES(read_attr("/sys/devices/system/cpu/present"),
+		 strcmp(buf, expected) == 0);
+	TEST_RES(read_attr("/sys/devices/system/cpu/cpu0/online"),
+		 strcmp(buf, "1\n") == 0);
+}
+END_TEST()
+
+FN_TEST(char_device)
+{
+	struct stat st;
+
+	TEST_RES(read_attr("/sys/class/mem/null/dev"),
+		 strcmp(buf, "1:3\n") == 0);
+	TEST_RES(read_attr("/sys/class/mem/null/uevent"),
+		 strcmp(buf, "MAJOR=1\nMINOR=3\nDEVNAME=null\n") == 0);
+
+	TEST_RES(read_link("/sys/class/mem/null"),
+		 strcmp(link_buf, "../../devices/virtual/mem/null") == 0);
+	TEST_RES(read_link("/sys/dev/char/1:3"),
+		 strcmp(link_buf, "../../devices/virtual/mem/null") == 0);
+	TEST_RES(read_link("/sys/devices/virtual/mem/null/subsystem"),
+		 strcmp(link_buf, "../../../../class/mem") == 0);
+
+	TEST_RES(stat("/sys/dev/char/1:3/subsystem", &st),
+		 S_ISDIR(st.st_mode));
+	TEST_RES(read_attr("/sys/dev/char/5:0/uevent"),
+		 strstr(buf, "DEVNAME=tty\n") != NULL);
+}
+END_TEST()
+
+FN_TEST(net_device)
+{
+	TEST_RES(read_attr("/sys/class/net/lo/ifindex"),
+		 strcmp(buf, "1\n") == 0);
+	TEST_RES(read_attr("/sys/class/net/lo/type"),
+		 strcmp(buf, "772\n") == 0);
+	TEST_RES(read_attr("/sys/class/net/lo/uevent"),
+		 strcmp(buf, "INTERFACE=lo\nIFINDEX=1\n") == 0);
+	TEST_RES(access("/sys/class/net/lo/dev", F_OK), _ret == -1);
+}
+END_TEST()
+
+FN_TEST(block_device)
+{
+	struct stat st;
+
+	TEST_SUCC(stat("/sys/block", &st));
+	TEST_SUCC(stat("/sys/dev/block", &st));
+	TEST_SUCC(stat("/sys/class/block", &st));
+}
+END_TEST()
+
+static ssize_t write_attr(const char *path, const char *value)
+{
+	int fd;
+	ssize_t len;
+
+	fd = open(path, O_WRONLY);
+	if (fd < 0)
+		return -1;
+
+	len = write(fd, value, strlen(value));
+	close(fd);
+
+	return len;
+}
+
+#define SCHEDULER_PATH "/sys/block/vda/queue/scheduler"
+
+FN_TEST(block_queue)
+{
+	if (access("/sys/block/vda", F_OK) < 0)
+		return;
+
+	TEST_RES(read_attr(SCHEDULER_PATH),
+		 strcmp(buf, "none [mq-deadline]\n") == 0);
+	TEST_RES(read_attr("/sys/block/vda/queue/max_segments"),
+		 atoi(buf) > 0);
+
+	TEST_RES(write_attr(SCHEDULER_PATH, "none\n"), _ret == 5);
+	TEST_RES(read_attr(SCHEDULER_PATH),
+		 strcmp(buf, "[none] mq-deadline\n") == 0);
+	TEST_ERRNO(write_attr(SCHEDULER_PATH, "cfq"), EINVAL);
+	TEST_RES(write_attr(SCHEDULER_PATH, "mq-deadline"), _ret == 11);
+	TEST_RES(read_attr(SCHEDULER_PATH),
+		 strcmp(buf, "none [mq-deadline]\n") == 0);
+}
+END_TEST()
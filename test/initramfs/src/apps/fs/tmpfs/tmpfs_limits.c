@@ -0,0 +1,152 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <errno.h>
+#include <fcntl.h>
+#include <sched.h>
+#include <string.h>
+#include <sys/mount.h>
+#include <sys/stat.h>
+#include <sys/statfs.h>
+#include <unistd.h>
+
+#include "../../common/test.h"
+
+#define TMPFS_DIR "/tmp/tmpfs_limits"
+#define TMPFS_MAGIC 0x01021994
+#define PAGE_SIZE 4096
+
+static char buf[PAGE_SIZE];
+
+static ssize_t write_file(const char *path, size_t nr_pages)
+{
+	ssize_t total = 0;
+	int fd = open(path, O_CREAT | O_WRONLY | O_TRUNC, 0644);
+	if (fd < 0)
+		return -1;
+
+	for (size_t i = 0; i < nr_pages; i++) {
+		ssize_t len = write(fd, buf, sizeof(buf));
+		if (len < 0) {
+			int saved_errno = errno;
+			close(fd);
+			errno = saved_errno;
+			return -1;
+		}
+		total += len;
+	}
+
+	if (close(fd) < 0)
+		return -1;
+	return total;
+}
+
+static int create_file(const char *path)
+{
+	int fd = open(path, O_CREAT | O_WRONLY, 0644);
+	if (fd < 0)
+		return -1;
+	return close(fd);
+}
+
+FN_SETUP(mount_tmpfs)
+{
+	CHECK(unshare(CLONE_NEWNS));
+	CHECK_WITH(mkdir(TMPFS_DIR, 0755), _ret >= 0 || errno == EEXIST);
+	memset(buf, 'a', sizeof(buf));
+}
+END_SETUP()
+
+FN_TEST(invalid_options)
+{
+	TEST_ERRNO(mount("tmpfs", TMPFS_DIR, "tmpfs", 0, "size=abc"), EINVAL);
+	TEST_ERRNO(mount("tmpfs", TMPFS_DIR, "tmpfs", 0, "mode=999"), EINVAL);
+	TEST_ERRNO(mount("tmpfs", TMPFS_DIR, "tmpfs", 0, "no_such_option"),
+		   EINVAL);
+}
+END_TEST()
+
+FN_TEST(root_options)
+{
+	struct stat st;
+
+	TEST_SUCC(mount("tmpfs", TMPFS_DIR, "tmpfs", 0,
+			"mode=700,uid=1000,gid=1001"));
+	TEST_RES(stat(TMPFS_DIR, &st),
+		 (st.st_mode & 07777) == 0700 && st.st_uid == 1000 &&
+			 st.st_gid == 1001);
+	TEST_SUCC(umount(TMPFS_DIR));
+}
+END_TEST()
+
+FN_TEST(size_limit)
+{
+	struct statfs sfs;
+
+	TEST_SUCC(mount("tmpfs", TMPFS_DIR, "tmpfs", 0, "size=16k"));
+	TEST_RES(statfs(TMPFS_DIR, &sfs),
+		 sfs.f_type == TMPFS_MAGIC && sfs.f_blocks == 4 &&
+			 sfs.f_bfree == 4);
+
+	TEST_RES(write_file(TMPFS_DIR "/file", 4), _ret == 4 * PAGE_SIZE);
+	TEST_RES(statfs(TMPFS_DIR, &sfs), sfs.f_bfree == 0);
+	TEST_ERRNO(write_file(TMPFS_DIR "/file2", 1), ENOSPC);
+	TEST_ERRNO(truncate(TMPFS_DIR "/file", 5 * PAGE_SIZE), ENOSPC);
+
+	// Freed blocks can be used again.
+	TEST_SUCC(truncate(TMPFS_DIR "/file", 2 * PAGE_SIZE));
+	TEST_RES(write_file(TMPFS_DIR "/file2", 2), _ret == 2 * PAGE_SIZE);
+	TEST_SUCC(unlink(TMPFS_DIR "/file"));
+	TEST_SUCC(unlink(TMPFS_DIR "/file2"));
+	TEST_RES(statfs(TMPFS_DIR, &sfs), sfs.f_bfree == 4);
+}
+END_TEST()
+
+FN_TEST(remount_size)
+{
+	struct statfs sfs;
+
+	TEST_RES(write_file(TMPFS_DIR "/file", 3), _ret == 3 * PAGE_SIZE);
+
+	// The new size cannot be lower than the usage.
+	TEST_ERRNO(mount(NULL, TMPFS_DIR, NULL, MS_REMOUNT, "size=8k"), EINVAL);
+
+	TEST_SUCC(mount(NULL, TMPFS_DIR, NULL, MS_REMOUNT, "size=32k"));
+	TEST_RES(statfs(TMPFS_DIR, &sfs),
+		 sfs.f_blocks == 8 && sfs.f_bfree == 5);
+	TEST_RES(write_file(TMPFS_DIR "/file2", 5), _ret == 5 * PAGE_SIZE);
+	TEST_ERRNO(write_file(TMPFS_DIR "/file3", 1), ENOSPC);
+
+	// An unlimited tmpfs cannot be limited again.
+	TEST_SUCC(mount(NULL, TMPFS_DIR, NULL, MS_REMOUNT, "size=0"));
+	TEST_RES(statfs(TMPFS_DIR, &sfs), sfs.f_blocks == 0);
+	TEST_ERRNO(mount(NULL, TMPFS_DIR, NULL, MS_REMOUNT, "size=1m"), EINVAL);
+	TEST_RES(write_file(TMPFS_DIR "/file3", 1), _ret == PAGE_SIZE);
+
+	TEST_SUCC(umount(TMPFS_DIR));
+}
+END_TEST()
+
+FN_TEST(nr_inodes_limit)
+{
+	struct statfs sfs;
+
+	// The root directory takes up one inode.
+	TEST_SUCC(mount("tmpfs", TMPFS_DIR, "tmpfs", 0, "nr_inodes=3"));
+	TEST_RES(statfs(TMPFS_DIR, &sfs), sfs.f_files == 3 && sfs.f_ffree == 2);
+
+	TEST_SUCC(create_file(TMPFS_DIR "/file"));
+	TEST_SUCC(mkdir(TMPFS_DIR "/dir", 0755));
+	TEST_ERRNO(create_file(TMPFS_DIR "/file2"), ENOSPC);
+	TEST_ERRNO(mkdir(TMPFS_DIR "/dir2", 0755), ENOSPC);
+
+	TEST_SUCC(unlink(TMPFS_DIR "/file"));
+	TEST_SUCC(create_file(TMPFS_DIR "/file2"));
+
+	TEST_SUCC(mount(NULL, TMPFS_DIR, NULL, MS_REMOUNT, "nr_inodes=4"));
+	TEST_SUCC(create_file(TMPFS_DIR "/file3"));
+
+	TEST_SUCC(umount(TMPFS_DIR));
+}
+END_TEST()
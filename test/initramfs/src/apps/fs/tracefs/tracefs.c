@@ -0,0 +1,159 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <sys/vfs.h>
+#include <unistd.h>
+
+#include "../../common/test.h"
+
+#define TRACEFS_MAGIC 0x74726163
+#define TRACEFS "/sys/kernel/tracing"
+#define SYS_ENTER TRACEFS "/events/raw_syscalls/sys_enter"
+
+static char buf[65536];
+
+static ssize_t read_file(const char *path)
+{
+	int fd;
+	ssize_t len;
+
+	fd = open(path, O_RDONLY);
+	if (fd < 0)
+		return -1;
+
+	len = read(fd, buf, sizeof(buf) - 1);
+	close(fd);
+	if (len >= 0)
+		buf[len] = '\0';
+
+	return len;
+}
+
+static ssize_t write_file(const char *path, const char *content, int flags)
+{
+	int fd;
+	ssize_t len;
+
+	fd = open(path, O_WRONLY | flags);
+	if (fd < 0)
+		return -1;
+
+	len = write(fd, content, strlen(content));
+	close(fd);
+
+	return len;
+}
+
+FN_TEST(statfs)
+{
+	struct statfs sfs;
+	struct stat st;
+
+	TEST_RES(statfs(TRACEFS, &sfs), sfs.f_type == TRACEFS_MAGIC);
+	TEST_RES(stat(TRACEFS, &st),
+		 S_ISDIR(st.st_mode) && (st.st_mode & 0777) == 0700);
+}
+END_TEST()
+
+FN_TEST(available_events)
+{
+	TEST_RES(read_file(TRACEFS "/available_events"),
+		 strstr(buf, "sched:sched_switch\n") != NULL &&
+			 strstr(buf, "raw_syscalls:sys_enter\n") != NULL &&
+			 strstr(buf, "raw_syscalls:sys_exit\n") != NULL);
+	TEST_RES(read_file(SYS_ENTER "/format"),
+		 strstr(buf, "name: sys_enter\n") != NULL);
+	TEST_RES(read_file(SYS_ENTER "/id"), buf[_ret - 1] == '\n');
+}
+END_TEST()
+
+FN_TEST(enable)
+{
+	TEST_RES(read_file(TRACEFS "/events/enable"), strcmp(buf, "0\n") == 0);
+	TEST_RES(read_file(SYS_ENTER "/enable"), strcmp(buf, "0\n") == 0);
+
+	TEST_RES(write_file(SYS_ENTER "/enable", "1\n", 0), _ret == 2);
+	TEST_RES(read_file(SYS_ENTER "/enable"), strcmp(buf, "1\n") == 0);
+	TEST_RES(read_file(TRACEFS "/events/raw_syscalls/enable"),
+		 strcmp(buf, "X\n") == 0);
+	TEST_RES(read_file(TRACEFS "/events/enable"), strcmp(buf, "X\n") == 0);
+
+	TEST_RES(write_file(TRACEFS "/events/raw_syscalls/enable", "0\n", 0),
+		 _ret == 2);
+	TEST_RES(read_file(SYS_ENTER "/enable"), strcmp(buf, "0\n") == 0);
+
+	TEST_ERRNO(write_file(SYS_ENTER "/enable", "2\n", 0), EINVAL);
+}
+END_TEST()
+
+FN_TEST(trace)
+{
+	char expected[64];
+	int fd;
+
+	snprintf(expected, sizeof(expected), "sys_enter: NR %d (", SYS_getppid);
+
+	// Open `trace` with `O_TRUNC` to discard the old events.
+	TEST_SUCC(write_file(TRACEFS "/trace", "", O_TRUNC));
+	TEST_RES(write_file(SYS_ENTER "/enable", "1\n", 0), _ret == 2);
+	TEST_SUCC(syscall(SYS_getppid));
+	TEST_RES(write_file(SYS_ENTER "/enable", "0\n", 0), _ret == 2);
+
+	// Reading `trace` does not consume the events.
+	TEST_RES(read_file(TRACEFS "/trace"),
+		 strncmp(buf, "# tracer: nop\n", 14) == 0 &&
+			 strstr(buf, expected) != NULL);
+	TEST_RES(read_file(TRACEFS "/trace"), strstr(buf, expected) != NULL);
+
+	// Reading `trace_pipe` consumes the events.
+	fd = TEST_SUCC(open(TRACEFS "/trace_pipe", O_RDONLY | O_NONBLOCK));
+	TEST_RES(read(fd, buf, sizeof(buf) - 1),
+		 _ret > 0 && (buf[_ret] = '\0', strstr(buf, expected) != NULL));
+	TEST_ERRNO(read(fd, buf, sizeof(buf) - 1), EAGAIN);
+	TEST_SUCC(close(fd));
+
+	TEST_RES(read_file(TRACEFS "/trace"), strstr(buf, expected) == NULL);
+}
+END_TEST()
+
+FN_TEST(tracing_on)
+{
+	char expected[64];
+
+	snprintf(expected, sizeof(expected), "sys_enter: NR %d (", SYS_getppid);
+
+	TEST_RES(read_file(TRACEFS "/tracing_on"), strcmp(buf, "1\n") == 0);
+	TEST_RES(write_file(TRACEFS "/tracing_on", "0\n", 0), _ret == 2);
+
+	TEST_SUCC(write_file(TRACEFS "/trace", "\n", 0));
+	TEST_RES(write_file(SYS_ENTER "/enable", "1\n", 0), _ret == 2);
+	TEST_SUCC(syscall(SYS_getppid));
+	TEST_RES(write_file(SYS_ENTER "/enable", "0\n", 0), _ret == 2);
+	TEST_RES(read_file(TRACEFS "/trace"), strstr(buf, expected) == NULL);
+
+	TEST_RES(write_file(TRACEFS "/tracing_on", "1\n", 0), _ret == 2);
+	TEST_RES(read_file(TRACEFS "/tracing_on"), strcmp(buf, "1\n") == 0);
+}
+END_TEST()
+
+FN_TEST(sched_switch)
+{
+	TEST_SUCC(write_file(TRACEFS "/trace", "", O_TRUNC));
+	TEST_RES(write_file(TRACEFS "/events/sched/enable", "1\n", 0),
+		 _ret == 2);
+	TEST_SUCC(usleep(10000));
+	TEST_RES(write_file(TRACEFS "/events/sched/enable", "0\n", 0),
+		 _ret == 2);
+
+	TEST_RES(read_file(TRACEFS "/trace"),
+		 strstr(buf, "sched_switch: prev_pid=") != NULL);
+	TEST_SUCC(write_file(TRACEFS "/trace", "", O_TRUNC));
+}
+END_TEST()
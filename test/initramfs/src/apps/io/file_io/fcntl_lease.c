@@ -0,0 +1,147 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <errno.h>
+#include <fcntl.h>
+#include <signal.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "../../common/test.h"
+
+#define TEST_FILE "/tmp/fcntl_lease_test"
+
+static volatile sig_atomic_t sigio_received;
+static volatile int lease_fd = -1;
+
+static void sigio_handler(int sig)
+{
+	(void)sig;
+
+	sigio_received = 1;
+	if (lease_fd >= 0)
+		fcntl(lease_fd, F_SETLEASE, F_UNLCK);
+}
+
+static int child_open(int flags)
+{
+	pid_t child = CHECK(fork());
+	if (child == 0) {
+		int fd = open(TEST_FILE, flags);
+
+		if (fd >= 0) {
+			_exit(0);
+		}
+
+		_exit(errno);
+	}
+
+	int status = 0;
+	CHECK(waitpid(child, &status, 0));
+	if (!WIFEXITED(status)) {
+		errno = ECHILD;
+		return -1;
+	}
+
+	return WEXITSTATUS(status);
+}
+
+FN_SETUP(create)
+{
+	int fd = CHECK(open(TEST_FILE, O_CREAT | O_RDWR | O_TRUNC, 0666));
+	CHECK(close(fd));
+
+	struct sigaction sa = { .sa_handler = sigio_handler,
+				.sa_flags = SA_RESTART };
+	CHECK(sigaction(SIGIO, &sa, NULL));
+}
+END_SETUP()
+
+FN_TEST(write_lease_conflicts_with_other_opens)
+{
+	int fd = TEST_SUCC(open(TEST_FILE, O_RDWR));
+	int fd2 = TEST_SUCC(open(TEST_FILE, O_RDONLY));
+
+	TEST_ERRNO(fcntl(fd, F_SETLEASE, F_WRLCK), EAGAIN);
+	TEST_SUCC(close(fd2));
+	TEST_SUCC(fcntl(fd, F_SETLEASE, F_WRLCK));
+	TEST_RES(fcntl(fd, F_GETLEASE), _ret == F_WRLCK);
+	TEST_SUCC(fcntl(fd, F_SETLEASE, F_UNLCK));
+	TEST_RES(fcntl(fd, F_GETLEASE), _ret == F_UNLCK);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(read_lease_conflicts_with_writable_opens)
+{
+	int fd = TEST_SUCC(open(TEST_FILE, O_RDONLY));
+	int fd2 = TEST_SUCC(open(TEST_FILE, O_WRONLY));
+
+	TEST_ERRNO(fcntl(fd, F_SETLEASE, F_RDLCK), EAGAIN);
+	TEST_SUCC(close(fd2));
+	TEST_SUCC(fcntl(fd, F_SETLEASE, F_RDLCK));
+	TEST_RES(fcntl(fd, F_GETLEASE), _ret == F_RDLCK);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(lease_on_directory)
+{
+	int fd = TEST_SUCC(open("/tmp", O_RDONLY | O_DIRECTORY));
+
+	TEST_ERRNO(fcntl(fd, F_SETLEASE, F_RDLCK), EINVAL);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(nonblocking_open_breaks_lease)
+{
+	int fd = TEST_SUCC(open(TEST_FILE, O_RDONLY));
+	sigio_received = 0;
+
+	TEST_SUCC(fcntl(fd, F_SETLEASE, F_RDLCK));
+
+	// A read-only open does not conflict with a read lease.
+	TEST_RES(child_open(O_RDONLY | O_NONBLOCK), _ret == 0);
+	TEST_RES(sigio_received, _ret == 0);
+
+	TEST_RES(child_open(O_WRONLY | O_NONBLOCK), _ret == EWOULDBLOCK);
+	TEST_RES(sigio_received, _ret == 1);
+	TEST_RES(fcntl(fd, F_GETLEASE), _ret == F_UNLCK);
+
+	// The lease can only be removed during the lease break.
+	TEST_ERRNO(fcntl(fd, F_SETLEASE, F_RDLCK), EAGAIN);
+	TEST_SUCC(fcntl(fd, F_SETLEASE, F_UNLCK));
+	TEST_RES(child_open(O_WRONLY | O_NONBLOCK), _ret == 0);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(blocking_open_waits_for_lease_break)
+{
+	int fd = TEST_SUCC(open(TEST_FILE, O_RDONLY));
+	sigio_received = 0;
+
+	TEST_SUCC(fcntl(fd, F_SETLEASE, F_RDLCK));
+	lease_fd = fd;
+
+	// The signal handler removes the lease, which unblocks the open.
+	TEST_RES(child_open(O_WRONLY), _ret == 0);
+	TEST_RES(sigio_received, _ret == 1);
+	TEST_RES(fcntl(fd, F_GETLEASE), _ret == F_UNLCK);
+
+	lease_fd = -1;
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(unlink(TEST_FILE));
+}
+END_SETUP()
@@ -95,3 +95,178 @@ FN_TEST(close_dup_fd_releases_locks)
 	TEST_SUCC(close(fd));
 }
 END_TEST()
+
+static int set_lock(int fd, int cmd, short type, off_t start, off_t len)
+{
+	struct flock lock = {
+		.l_type = type,
+		.l_whence = SEEK_SET,
+		.l_start = start,
+		.l_len = len,
+	};
+
+	return fcntl(fd, cmd, &lock);
+}
+
+FN_TEST(ofd_locks_conflict_with_posix_locks)
+{
+	int fd = TEST_SUCC(open(TEST_FILE, O_RDWR));
+	int another_fd = TEST_SUCC(open(TEST_FILE, O_RDWR));
+	struct flock lock = {
+		.l_type = F_WRLCK,
+		.l_whence = SEEK_SET,
+		.l_start = 0,
+		.l_len = 10,
+	};
+
+	TEST_SUCC(set_lock(fd, F_OFD_SETLK, F_WRLCK, 0, 10));
+	TEST_ERRNO(set_lock(another_fd, F_SETLK, F_WRLCK, 0, 10), EAGAIN);
+	TEST_ERRNO(set_lock(another_fd, F_OFD_SETLK, F_RDLCK, 5, 10), EAGAIN);
+
+	TEST_RES(fcntl(another_fd, F_GETLK, &lock),
+		 lock.l_type == F_WRLCK && lock.l_pid == -1);
+
+	TEST_SUCC(close(fd));
+	TEST_SUCC(set_lock(another_fd, F_OFD_SETLK, F_WRLCK, 0, 10));
+	TEST_SUCC(close(another_fd));
+}
+END_TEST()
+
+FN_TEST(ofd_locks_survive_closing_dup_fd)
+{
+	int fd = TEST_SUCC(open(TEST_FILE, O_RDWR));
+	int duplicated_fd = TEST_SUCC(dup(fd));
+
+	TEST_SUCC(set_lock(fd, F_OFD_SETLK, F_WRLCK, 0, 100));
+	TEST_SUCC(close(duplicated_fd));
+	TEST_RES(child_try_write_lock(0, 100), _ret == EAGAIN);
+
+	TEST_SUCC(set_lock(fd, F_OFD_SETLK, F_UNLCK, 0, 100));
+	TEST_RES(child_try_write_lock(0, 100), _ret == 0);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(ofd_lock_with_pid)
+{
+	int fd = TEST_SUCC(open(TEST_FILE, O_RDWR));
+	struct flock lock = {
+		.l_type = F_WRLCK,
+		.l_whence = SEEK_SET,
+		.l_start = 0,
+		.l_len = 10,
+		.l_pid = getpid(),
+	};
+
+	TEST_ERRNO(fcntl(fd, F_OFD_SETLK, &lock), EINVAL);
+	TEST_ERRNO(fcntl(fd, F_OFD_GETLK, &lock), EINVAL);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(exit_releases_locks)
+{
+	int fd = TEST_SUCC(open(TEST_FILE, O_RDWR));
+	struct flock lock = {
+		.l_type = F_WRLCK,
+		.l_whence = SEEK_SET,
+		.l_start = 0,
+		.l_len = 10,
+	};
+
+	// The child shares the open file description, which outlives the child.
+	pid_t child = TEST_SUCC(fork());
+	if (child == 0) {
+		CHECK(set_lock(fd, F_SETLK, F_WRLCK, 0, 10));
+		_exit(0);
+	}
+	TEST_RES(waitpid(child, NULL, 0), _ret == child);
+
+	TEST_RES(fcntl(fd, F_GETLK, &lock), lock.l_type == F_UNLCK);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(deadlock_detection)
+{
+	int fd = TEST_SUCC(open(TEST_FILE, O_RDWR));
+	int pipe_fds[2];
+	char byte = 0;
+
+	TEST_SUCC(pipe(pipe_fds));
+	TEST_SUCC(set_lock(fd, F_SETLK, F_WRLCK, 0, 10));
+
+	pid_t child = TEST_SUCC(fork());
+	if (child == 0) {
+		int child_fd = CHECK(open(TEST_FILE, O_RDWR));
+		CHECK(set_lock(child_fd, F_SETLK, F_WRLCK, 10, 10));
+		CHECK(write(pipe_fds[1], &byte, 1));
+		// Blocks until the parent releases its lock.
+		CHECK(set_lock(child_fd, F_SETLKW, F_WRLCK, 0, 10));
+		_exit(0);
+	}
+
+	TEST_RES(read(pipe_fds[0], &byte, 1), _ret == 1);
+	// Waits for the child to be blocked.
+	usleep(100 * 1000);
+
+	TEST_ERRNO(set_lock(fd, F_SETLKW, F_WRLCK, 10, 10), EDEADLK);
+
+	TEST_SUCC(set_lock(fd, F_SETLK, F_UNLCK, 0, 10));
+	TEST_RES(waitpid(child, NULL, 0), _ret == child);
+
+	TEST_SUCC(close(pipe_fds[0]));
+	TEST_SUCC(close(pipe_fds[1]));
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(deadlock_detection_three_processes)
+{
+	int fd = TEST_SUCC(open(TEST_FILE, O_RDWR));
+	int pipe_fds[2];
+	char byte = 0;
+
+	TEST_SUCC(pipe(pipe_fds));
+	TEST_SUCC(set_lock(fd, F_SETLK, F_WRLCK, 0, 10));
+
+	pid_t child_b = TEST_SUCC(fork());
+	if (child_b == 0) {
+		int child_fd = CHECK(open(TEST_FILE, O_RDWR));
+		CHECK(set_lock(child_fd, F_SETLK, F_WRLCK, 20, 10));
+		CHECK(write(pipe_fds[1], &byte, 1));
+		// Blocks until the parent releases its lock.
+		CHECK(set_lock(child_fd, F_SETLKW, F_WRLCK, 0, 10));
+		_exit(0);
+	}
+	TEST_RES(read(pipe_fds[0], &byte, 1), _ret == 1);
+
+	pid_t child_a = TEST_SUCC(fork());
+	if (child_a == 0) {
+		int child_fd = CHECK(open(TEST_FILE, O_RDWR));
+		CHECK(set_lock(child_fd, F_SETLK, F_WRLCK, 10, 10));
+		CHECK(write(pipe_fds[1], &byte, 1));
+		// Blocks until the child B exits.
+		CHECK(set_lock(child_fd, F_SETLKW, F_WRLCK, 20, 10));
+		_exit(0);
+	}
+	TEST_RES(read(pipe_fds[0], &byte, 1), _ret == 1);
+
+	// Waits for both children to be blocked.
+	usleep(100 * 1000);
+
+	// The parent waits for the child A, which waits for the child B, which waits for the parent.
+	TEST_ERRNO(set_lock(fd, F_SETLKW, F_WRLCK, 10, 10), EDEADLK);
+
+	TEST_SUCC(set_lock(fd, F_SETLK, F_UNLCK, 0, 10));
+	TEST_RES(waitpid(child_b, NULL, 0), _ret == child_b);
+	TEST_RES(waitpid(child_a, NULL, 0), _ret == child_a);
+
+	TEST_SUCC(close(pipe_fds[0]));
+	TEST_SUCC(close(pipe_fds[1]));
+	TEST_SUCC(close(fd));
+}
+END_TEST()
@@ -11,6 +11,7 @@ set -e
 ./eventfd2/eventfd2
 
 ./file_io/access_err
+./file_io/fcntl_lease
 ./file_io/fcntl_lock
 ./file_io/file_err
 ./file_io/iovec_err
@@ -0,0 +1,329 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <unistd.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <linux/if_alg.h>
+
+#include "../common/test.h"
+
+#ifndef SOL_ALG
+#define SOL_ALG 279
+#endif
+
+static int alg_socket(const char *type, const char *name)
+{
+	struct sockaddr_alg addr = { .salg_family = AF_ALG };
+	int sk;
+
+	strncpy((char *)addr.salg_type, type, sizeof(addr.salg_type));
+	strncpy((char *)addr.salg_name, name, sizeof(addr.salg_name));
+
+	sk = socket(AF_ALG, SOCK_SEQPACKET, 0);
+	if (sk < 0)
+		return sk;
+	if (bind(sk, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+		close(sk);
+		return -1;
+	}
+
+	return sk;
+}
+
+static int set_key(int sk, const void *key, socklen_t len)
+{
+	return setsockopt(sk, SOL_ALG, ALG_SET_KEY, key, len);
+}
+
+static ssize_t send_op(int sk, int op, const void *iv, size_t iv_len,
+		       int assoc_len, const void *buf, size_t len, int flags)
+{
+	char control[CMSG_SPACE(sizeof(int)) * 2 + CMSG_SPACE(64)] = {};
+	struct iovec iov = { .iov_base = (void *)buf, .iov_len = len };
+	struct msghdr msg = {
+		.msg_iov = &iov,
+		.msg_iovlen = 1,
+		.msg_control = control,
+		.msg_controllen = sizeof(control),
+	};
+	struct cmsghdr *cmsg;
+	struct af_alg_iv *alg_iv;
+
+	cmsg = CMSG_FIRSTHDR(&msg);
+	cmsg->cmsg_level = SOL_ALG;
+	cmsg->cmsg_type = ALG_SET_OP;
+	cmsg->cmsg_len = CMSG_LEN(sizeof(int));
+	memcpy(CMSG_DATA(cmsg), &op, sizeof(int));
+
+	cmsg = CMSG_NXTHDR(&msg, cmsg);
+	cmsg->cmsg_level = SOL_ALG;
+	cmsg->cmsg_type = ALG_SET_AEAD_ASSOCLEN;
+	cmsg->cmsg_len = CMSG_LEN(sizeof(int));
+	memcpy(CMSG_DATA(cmsg), &assoc_len, sizeof(int));
+
+	cmsg = CMSG_NXTHDR(&msg, cmsg);
+	cmsg->cmsg_level = SOL_ALG;
+	cmsg->cmsg_type = ALG_SET_IV;
+	cmsg->cmsg_len = CMSG_LEN(sizeof(*alg_iv) + iv_len);
+	alg_iv = (struct af_alg_iv *)CMSG_DATA(cmsg);
+	alg_iv->ivlen = iv_len;
+	memcpy(alg_iv->iv, iv, iv_len);
+
+	msg.msg_controllen = (char *)cmsg - control + CMSG_SPACE(cmsg->cmsg_len);
+	return sendmsg(sk, &msg, flags);
+}
+
+// SHA-256("abc")
+static const unsigned char sha256_abc[32] = {
+	0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40,
+	0xde, 0x5d, 0xae, 0x22, 0x23, 0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17,
+	0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61, 0xf2, 0x00, 0x15, 0xad,
+};
+
+// SHA-256("")
+static const unsigned char sha256_empty[32] = {
+	0xe3, 0xb0, 0xc4, 0x42, 0x98, 0xfc, 0x1c, 0x14, 0x9a, 0xfb, 0xf4,
+	0xc8, 0x99, 0x6f, 0xb9, 0x24, 0x27, 0xae, 0x41, 0xe4, 0x64, 0x9b,
+	0x93, 0x4c, 0xa4, 0x95, 0x99, 0x1b, 0x78, 0x52, 0xb8, 0x55,
+};
+
+FN_TEST(bind)
+{
+	struct sockaddr_alg addr = { .salg_family = AF_ALG };
+	int sk;
+
+	TEST_ERRNO(socket(AF_ALG, SOCK_STREAM, 0), ESOCKTNOSUPPORT);
+
+	sk = TEST_SUCC(socket(AF_ALG, SOCK_SEQPACKET, 0));
+	TEST_ERRNO(accept(sk, NULL, NULL), EINVAL);
+
+	strcpy((char *)addr.salg_type, "hash");
+	strcpy((char *)addr.salg_name, "no-such-hash");
+	TEST_ERRNO(bind(sk, (struct sockaddr *)&addr, sizeof(addr)), ENOENT);
+
+	strcpy((char *)addr.salg_type, "no-such-type");
+	strcpy((char *)addr.salg_name, "sha256");
+	TEST_ERRNO(bind(sk, (struct sockaddr *)&addr, sizeof(addr)), ENOENT);
+
+	strcpy((char *)addr.salg_type, "hash");
+	addr.salg_feat = 1;
+	TEST_ERRNO(bind(sk, (struct sockaddr *)&addr, sizeof(addr)), EINVAL);
+
+	addr.salg_feat = 0;
+	TEST_SUCC(bind(sk, (struct sockaddr *)&addr, sizeof(addr)));
+	TEST_ERRNO(set_key(sk, "key", 3), ENOSYS);
+
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(hash)
+{
+	unsigned char digest[32];
+	int sk, op_sk, dup_sk;
+
+	sk = TEST_SUCC(alg_socket("hash", "sha256"));
+	op_sk = TEST_SUCC(accept(sk, NULL, NULL));
+
+	TEST_RES(send(op_sk, "abc", 3, 0), _ret == 3);
+	TEST_RES(recv(op_sk, digest, sizeof(digest), 0),
+		 _ret == 32 && memcmp(digest, sha256_abc, 32) == 0);
+
+	TEST_RES(recv(op_sk, digest, sizeof(digest), 0),
+		 _ret == 32 && memcmp(digest, sha256_empty, 32) == 0);
+
+	TEST_RES(send(op_sk, "a", 1, MSG_MORE), _ret == 1);
+	dup_sk = TEST_SUCC(accept(op_sk, NULL, NULL));
+	TEST_RES(send(op_sk, "bc", 2, 0), _ret == 2);
+	TEST_RES(recv(op_sk, digest, 8, 0),
+		 _ret == 8 && memcmp(digest, sha256_abc, 8) == 0);
+
+	TEST_RES(send(dup_sk, "bc", 2, MSG_MORE), _ret == 2);
+	TEST_RES(recv(dup_sk, digest, sizeof(digest), 0),
+		 _ret == 32 && memcmp(digest, sha256_abc, 32) == 0);
+
+	TEST_ERRNO(set_key(op_sk, "key", 3), ENOPROTOOPT);
+
+	TEST_SUCC(close(dup_sk));
+	TEST_SUCC(close(op_sk));
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(hmac)
+{
+	// RFC 4231, test case 1
+	static const unsigned char expected[32] = {
+		0xb0, 0x34, 0x4c, 0x61, 0xd8, 0xdb, 0x38, 0x53, 0x5c, 0xa8, 0xaf,
+		0xce, 0xaf, 0x0b, 0xf1, 0x2b, 0x88, 0x1d, 0xc2, 0x00, 0xc9, 0x83,
+		0x3d, 0xa7, 0x26, 0xe9, 0x37, 0x6c, 0x2e, 0x32, 0xcf, 0xf7,
+	};
+	unsigned char key[20], digest[32];
+	int sk, op_sk;
+
+	memset(key, 0x0b, sizeof(key));
+
+	sk = TEST_SUCC(alg_socket("hash", "hmac(sha256)"));
+
+	// The operation socket can be created before the key is set.
+	op_sk = TEST_SUCC(accept(sk, NULL, NULL));
+	TEST_ERRNO(send(op_sk, "Hi ", 3, 0), ENOKEY);
+
+	TEST_SUCC(set_key(sk, key, sizeof(key)));
+
+	TEST_RES(send(op_sk, "Hi ", 3, MSG_MORE), _ret == 3);
+	TEST_ERRNO(set_key(sk, key, sizeof(key)), EBUSY);
+	TEST_RES(send(op_sk, "There", 5, 0), _ret == 5);
+	TEST_RES(recv(op_sk, digest, sizeof(digest), 0),
+		 _ret == 32 && memcmp(digest, expected, 32) == 0);
+
+	TEST_SUCC(close(op_sk));
+	TEST_SUCC(set_key(sk, key, sizeof(key)));
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(skcipher)
+{
+	// NIST SP 800-38A, F.2.1 CBC-AES128.Encrypt
+	static const unsigned char key[16] = {
+		0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6,
+		0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c,
+	};
+	static const unsigned char iv[16] = {
+		0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07,
+		0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+	};
+	static const unsigned char plaintext[32] = {
+		0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96,
+		0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93, 0x17, 0x2a,
+		0xae, 0x2d, 0x8a, 0x57, 0x1e, 0x03, 0xac, 0x9c,
+		0x9e, 0xb7, 0x6f, 0xac, 0x45, 0xaf, 0x8e, 0x51,
+	};
+	static const unsigned char ciphertext[32] = {
+		0x76, 0x49, 0xab, 0xac, 0x81, 0x19, 0xb2, 0x46,
+		0xce, 0xe9, 0x8e, 0x9b, 0x12, 0xe9, 0x19, 0x7d,
+		0x50, 0x86, 0xcb, 0x9b, 0x50, 0x72, 0x19, 0xee,
+		0x95, 0xdb, 0x11, 0x3a, 0x91, 0x76, 0x78, 0xb2,
+	};
+	unsigned char buf[32];
+	int sk, op_sk;
+
+	sk = TEST_SUCC(alg_socket("skcipher", "cbc(aes)"));
+	op_sk = TEST_SUCC(accept(sk, NULL, NULL));
+	TEST_ERRNO(recv(op_sk, buf, sizeof(buf), 0), ENOKEY);
+	TEST_ERRNO(set_key(sk, key, 15), EINVAL);
+	TEST_SUCC(set_key(sk, key, sizeof(key)));
+
+	TEST_ERRNO(send_op(op_sk, ALG_OP_ENCRYPT, iv, 8, 0, plaintext, 16, 0),
+		   EINVAL);
+	TEST_ERRNO(send_op(op_sk, 2, iv, 16, 0, plaintext, 16, 0), EINVAL);
+
+	// The IV is chained between the requests.
+	TEST_RES(send_op(op_sk, ALG_OP_ENCRYPT, iv, 16, 0, plaintext, 16,
+			 MSG_MORE),
+		 _ret == 16);
+	TEST_RES(recv(op_sk, buf, 16, 0),
+		 _ret == 16 && memcmp(buf, ciphertext, 16) == 0);
+	TEST_RES(send(op_sk, plaintext + 16, 16, 0), _ret == 16);
+	TEST_RES(recv(op_sk, buf, 16, 0),
+		 _ret == 16 && memcmp(buf, ciphertext + 16, 16) == 0);
+
+	TEST_RES(send_op(op_sk, ALG_OP_DECRYPT, iv, 16, 0, ciphertext, 32, 0),
+		 _ret == 32);
+	TEST_ERRNO(send(op_sk, ciphertext, 16, 0), EINVAL);
+	TEST_RES(recv(op_sk, buf, sizeof(buf), 0),
+		 _ret == 32 && memcmp(buf, plaintext, 32) == 0);
+
+	// Incomplete blocks cannot be processed.
+	TEST_RES(send_op(op_sk, ALG_OP_ENCRYPT, iv, 16, 0, plaintext, 15, 0),
+		 _ret == 15);
+	TEST_ERRNO(recv(op_sk, buf, sizeof(buf), 0), EINVAL);
+
+	TEST_SUCC(close(op_sk));
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(aead)
+{
+	// The test case 2 of the GCM specification
+	static const unsigned char key[16] = {};
+	static const unsigned char iv[12] = {};
+	static const unsigned char plaintext[16] = {};
+	static const unsigned char ciphertext[32] = {
+		0x03, 0x88, 0xda, 0xce, 0x60, 0xb6, 0xa3, 0x92,
+		0xf3, 0x28, 0xc2, 0xb9, 0x71, 0xb2, 0xfe, 0x78,
+		0xab, 0x6e, 0x47, 0xd4, 0x2c, 0xec, 0x13, 0xbd,
+		0xf5, 0x3a, 0x67, 0xb2, 0x12, 0x57, 0xbd, 0xdf,
+	};
+	unsigned char buf[40];
+	int sk, op_sk;
+
+	sk = TEST_SUCC(alg_socket("aead", "gcm(aes)"));
+	TEST_SUCC(set_key(sk, key, sizeof(key)));
+	TEST_ERRNO(setsockopt(sk, SOL_ALG, ALG_SET_AEAD_AUTHSIZE, NULL, 5),
+		   EINVAL);
+	op_sk = TEST_SUCC(accept(sk, NULL, NULL));
+
+	TEST_RES(send_op(op_sk, ALG_OP_ENCRYPT, iv, 12, 0, plaintext, 16, 0),
+		 _ret == 16);
+	TEST_RES(recv(op_sk, buf, sizeof(buf), 0),
+		 _ret == 32 && memcmp(buf, ciphertext, 32) == 0);
+
+	TEST_RES(send_op(op_sk, ALG_OP_DECRYPT, iv, 12, 0, ciphertext, 32, 0),
+		 _ret == 32);
+	TEST_RES(recv(op_sk, buf, sizeof(buf), 0),
+		 _ret == 16 && memcmp(buf, plaintext, 16) == 0);
+
+	memcpy(buf, ciphertext, 32);
+	buf[31] ^= 1;
+	TEST_RES(send_op(op_sk, ALG_OP_DECRYPT, iv, 12, 0, buf, 32, 0),
+		 _ret == 32);
+	TEST_ERRNO(recv(op_sk, buf, sizeof(buf), 0), EBADMSG);
+
+	// The associated data is copied to the output.
+	memcpy(buf, "AAAA", 4);
+	memcpy(buf + 4, plaintext, 16);
+	TEST_RES(send_op(op_sk, ALG_OP_ENCRYPT, iv, 12, 4, buf, 20, 0),
+		 _ret == 20);
+	TEST_RES(recv(op_sk, buf, sizeof(buf), 0),
+		 _ret == 36 && memcmp(buf, "AAAA", 4) == 0 &&
+			 memcmp(buf + 4, ciphertext, 16) == 0);
+
+	// The input is too short to contain the authentication tag.
+	TEST_RES(send_op(op_sk, ALG_OP_DECRYPT, iv, 12, 4, ciphertext, 16, 0),
+		 _ret == 16);
+	TEST_ERRNO(recv(op_sk, buf, sizeof(buf), 0), EINVAL);
+
+	TEST_SUCC(close(op_sk));
+
+	TEST_SUCC(setsockopt(sk, SOL_ALG, ALG_SET_AEAD_AUTHSIZE, NULL, 8));
+	op_sk = TEST_SUCC(accept(sk, NULL, NULL));
+	TEST_RES(send_op(op_sk, ALG_OP_ENCRYPT, iv, 12, 0, plaintext, 16, 0),
+		 _ret == 16);
+	TEST_RES(recv(op_sk, buf, sizeof(buf), 0),
+		 _ret == 24 && memcmp(buf, ciphertext, 24) == 0);
+	TEST_SUCC(close(op_sk));
+
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(rng)
+{
+	unsigned char buf[256];
+	int sk, op_sk;
+
+	sk = TEST_SUCC(alg_socket("rng", "stdrng"));
+	op_sk = TEST_SUCC(accept(sk, NULL, NULL));
+
+	TEST_RES(recv(op_sk, buf, sizeof(buf), 0), _ret == 128);
+	TEST_ERRNO(send(op_sk, buf, 16, 0), EOPNOTSUPP);
+
+	TEST_SUCC(close(op_sk));
+	TEST_SUCC(close(sk));
+}
+END_TEST()
@@ -0,0 +1,158 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <unistd.h>
+#include <string.h>
+#include <net/if.h>
+#include <sys/socket.h>
+#include <netinet/in.h>
+#include <arpa/inet.h>
+
+#include "../common/test.h"
+
+#ifndef IP_FREEBIND
+#define IP_FREEBIND 15
+#endif
+
+static struct sockaddr_in addr;
+static socklen_t addrlen = sizeof(addr);
+
+static struct msghdr msg;
+
+FN_SETUP(init)
+{
+	addr.sin_family = AF_INET;
+	CHECK(inet_aton("127.0.0.1", &addr.sin_addr));
+}
+END_SETUP()
+
+static int set_ip_option(int sk, int name, int value)
+{
+	return setsockopt(sk, SOL_IP, name, &value, sizeof(value));
+}
+
+static int get_ip_option(int sk, int name)
+{
+	int value;
+	socklen_t len = sizeof(value);
+
+	if (getsockopt(sk, SOL_IP, name, &value, &len) < 0)
+		return -1;
+
+	return value;
+}
+
+static int recv_msg(int sk)
+{
+	static char buf[16];
+	static char control[256];
+	static struct iovec iov = { .iov_base = buf, .iov_len = sizeof(buf) };
+
+	memset(&msg, 0, sizeof(msg));
+	msg.msg_iov = &iov;
+	msg.msg_iovlen = 1;
+	msg.msg_control = control;
+	msg.msg_controllen = sizeof(control);
+
+	return recvmsg(sk, &msg, 0);
+}
+
+static int check_pktinfo(void)
+{
+	struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+	struct in_pktinfo *info;
+
+	if (cmsg == NULL || cmsg->cmsg_level != SOL_IP ||
+	    cmsg->cmsg_type != IP_PKTINFO ||
+	    cmsg->cmsg_len != CMSG_LEN(sizeof(struct in_pktinfo)))
+		return 0;
+
+	info = (struct in_pktinfo *)CMSG_DATA(cmsg);
+	return info->ipi_ifindex == (int)if_nametoindex("lo") &&
+	       info->ipi_spec_dst.s_addr == htonl(INADDR_LOOPBACK) &&
+	       info->ipi_addr.s_addr == htonl(INADDR_LOOPBACK);
+}
+
+FN_TEST(bind_to_device)
+{
+	int sk = TEST_SUCC(socket(AF_INET, SOCK_DGRAM, 0));
+	char name[IFNAMSIZ];
+	socklen_t len = sizeof(name);
+
+	TEST_RES(getsockopt(sk, SOL_SOCKET, SO_BINDTODEVICE, name, &len),
+		 len == 0);
+
+	TEST_ERRNO(setsockopt(sk, SOL_SOCKET, SO_BINDTODEVICE, "none", 5),
+		   ENODEV);
+	TEST_SUCC(setsockopt(sk, SOL_SOCKET, SO_BINDTODEVICE, "lo", 3));
+
+	len = 1;
+	TEST_ERRNO(getsockopt(sk, SOL_SOCKET, SO_BINDTODEVICE, name, &len),
+		   EINVAL);
+	len = sizeof(name);
+	TEST_RES(getsockopt(sk, SOL_SOCKET, SO_BINDTODEVICE, name, &len),
+		 len == 3 && strcmp(name, "lo") == 0);
+
+	addr.sin_port = 0;
+	TEST_SUCC(bind(sk, (struct sockaddr *)&addr, addrlen));
+
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(freebind)
+{
+	int sk = TEST_SUCC(socket(AF_INET, SOCK_DGRAM, 0));
+	struct sockaddr_in nonlocal = { .sin_family = AF_INET };
+
+	CHECK(inet_aton("192.0.2.1", &nonlocal.sin_addr));
+
+	TEST_RES(get_ip_option(sk, IP_FREEBIND), _ret == 0);
+	TEST_ERRNO(bind(sk, (struct sockaddr *)&nonlocal, sizeof(nonlocal)),
+		   EADDRNOTAVAIL);
+
+	TEST_SUCC(set_ip_option(sk, IP_FREEBIND, 1));
+	TEST_RES(get_ip_option(sk, IP_FREEBIND), _ret == 1);
+	TEST_SUCC(bind(sk, (struct sockaddr *)&nonlocal, sizeof(nonlocal)));
+
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(ttl)
+{
+	int sk = TEST_SUCC(socket(AF_INET, SOCK_STREAM, 0));
+
+	TEST_RES(get_ip_option(sk, IP_TTL), _ret == 64);
+	TEST_SUCC(set_ip_option(sk, IP_TTL, 32));
+	TEST_RES(get_ip_option(sk, IP_TTL), _ret == 32);
+	TEST_ERRNO(set_ip_option(sk, IP_TTL, 256), EINVAL);
+
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(pktinfo)
+{
+	int sk_send = TEST_SUCC(socket(AF_INET, SOCK_DGRAM, 0));
+	int sk_recv = TEST_SUCC(socket(AF_INET, SOCK_DGRAM, 0));
+
+	addr.sin_port = 0;
+	TEST_SUCC(bind(sk_recv, (struct sockaddr *)&addr, addrlen));
+	TEST_SUCC(getsockname(sk_recv, (struct sockaddr *)&addr, &addrlen));
+	TEST_SUCC(connect(sk_send, (struct sockaddr *)&addr, addrlen));
+
+	TEST_RES(get_ip_option(sk_recv, IP_PKTINFO), _ret == 0);
+	TEST_RES(send(sk_send, "abc", 3, 0), _ret == 3);
+	TEST_RES(recv_msg(sk_recv), _ret == 3 && msg.msg_controllen == 0);
+
+	TEST_SUCC(set_ip_option(sk_recv, IP_PKTINFO, 1));
+	TEST_RES(get_ip_option(sk_recv, IP_PKTINFO), _ret == 1);
+	TEST_RES(send(sk_send, "abc", 3, 0), _ret == 3);
+	TEST_RES(recv_msg(sk_recv), _ret == 3 && check_pktinfo());
+
+	TEST_SUCC(close(sk_send));
+	TEST_SUCC(close(sk_recv));
+}
+END_TEST()
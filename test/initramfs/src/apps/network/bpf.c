@@ -0,0 +1,393 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+#include <poll.h>
+#include <stddef.h>
+#include <string.h>
+#include <unistd.h>
+#include <sys/mman.h>
+#include <sys/socket.h>
+#include <sys/syscall.h>
+#include <arpa/inet.h>
+#include <net/ethernet.h>
+#include <netinet/in.h>
+#include <netinet/ip.h>
+#include <linux/bpf.h>
+#include <linux/if_packet.h>
+
+#include "../common/test.h"
+
+// The loopback iface is always the first iface.
+#define LO_IFINDEX 1
+
+// An experimental protocol number, which is not used by other tests.
+#define TEST_PROTO 254
+
+#define PAYLOAD "EBPF!"
+#define PAYLOAD_LEN (sizeof(PAYLOAD) - 1)
+
+#define RINGBUF_SIZE 4096
+
+#define INSN(c, d, s, o, i)                                        \
+	((struct bpf_insn){ .code = (c),                           \
+			    .dst_reg = (d),                        \
+			    .src_reg = (s),                        \
+			    .off = (o),                            \
+			    .imm = (i) })
+#define INSN_LD_MAP_FD(d, fd)                                      \
+	INSN(BPF_LD | BPF_IMM | BPF_DW, d, BPF_PSEUDO_MAP_FD, 0, fd), \
+		INSN(0, 0, 0, 0, 0)
+#define INSN_EXIT() INSN(BPF_JMP | BPF_EXIT, 0, 0, 0, 0)
+
+static int sys_bpf(int cmd, union bpf_attr *attr)
+{
+	return syscall(SYS_bpf, cmd, attr, sizeof(*attr));
+}
+
+static int create_map(int type, int key_size, int value_size, int max_entries)
+{
+	union bpf_attr attr;
+
+	memset(&attr, 0, sizeof(attr));
+	attr.map_type = type;
+	attr.key_size = key_size;
+	attr.value_size = value_size;
+	attr.max_entries = max_entries;
+
+	return sys_bpf(BPF_MAP_CREATE, &attr);
+}
+
+static int map_elem_op(int cmd, int fd, const void *key, void *value,
+		       unsigned long flags)
+{
+	union bpf_attr attr;
+
+	memset(&attr, 0, sizeof(attr));
+	attr.map_fd = fd;
+	attr.key = (unsigned long)key;
+	attr.value = (unsigned long)value;
+	attr.flags = flags;
+
+	return sys_bpf(cmd, &attr);
+}
+
+static int lookup_elem(int fd, const void *key, void *value)
+{
+	return map_elem_op(BPF_MAP_LOOKUP_ELEM, fd, key, value, 0);
+}
+
+static int update_elem(int fd, const void *key, const void *value,
+		       unsigned long flags)
+{
+	return map_elem_op(BPF_MAP_UPDATE_ELEM, fd, key, (void *)value, flags);
+}
+
+static int delete_elem(int fd, const void *key)
+{
+	return map_elem_op(BPF_MAP_DELETE_ELEM, fd, key, NULL, 0);
+}
+
+static int get_next_key(int fd, const void *key, void *next_key)
+{
+	return map_elem_op(BPF_MAP_GET_NEXT_KEY, fd, key, next_key, 0);
+}
+
+static int load_prog(const struct bpf_insn *insns, int insn_cnt)
+{
+	union bpf_attr attr;
+
+	memset(&attr, 0, sizeof(attr));
+	attr.prog_type = BPF_PROG_TYPE_SOCKET_FILTER;
+	attr.insns = (unsigned long)insns;
+	attr.insn_cnt = insn_cnt;
+	attr.license = (unsigned long)"GPL";
+
+	return sys_bpf(BPF_PROG_LOAD, &attr);
+}
+
+#define LOAD_PROG(...)                                             \
+	({                                                         \
+		struct bpf_insn __insns[] = { __VA_ARGS__ };       \
+		load_prog(__insns,                                 \
+			  sizeof(__insns) / sizeof(__insns[0]));   \
+	})
+
+FN_TEST(array_map)
+{
+	unsigned int key;
+	unsigned long value;
+	int fd;
+
+	TEST_ERRNO(create_map(BPF_MAP_TYPE_ARRAY, 8, 8, 4), EINVAL);
+	TEST_ERRNO(create_map(BPF_MAP_TYPE_ARRAY, 4, 8, 0), EINVAL);
+	fd = TEST_SUCC(create_map(BPF_MAP_TYPE_ARRAY, 4, 8, 4));
+
+	// All the elements exist and are zeroed initially.
+	key = 3;
+	value = 1;
+	TEST_RES(lookup_elem(fd, &key, &value), value == 0);
+	key = 4;
+	TEST_ERRNO(lookup_elem(fd, &key, &value), ENOENT);
+
+	key = 1;
+	value = 42;
+	TEST_SUCC(update_elem(fd, &key, &value, BPF_ANY));
+	TEST_SUCC(update_elem(fd, &key, &value, BPF_EXIST));
+	TEST_ERRNO(update_elem(fd, &key, &value, BPF_NOEXIST), EEXIST);
+	TEST_ERRNO(update_elem(fd, &key, &value, BPF_EXIST + 1), EINVAL);
+	value = 0;
+	TEST_RES(lookup_elem(fd, &key, &value), value == 42);
+
+	key = 4;
+	TEST_ERRNO(update_elem(fd, &key, &value, BPF_ANY), E2BIG);
+	key = 1;
+	TEST_ERRNO(delete_elem(fd, &key), EINVAL);
+
+	TEST_RES(get_next_key(fd, NULL, &key), key == 0);
+	key = 2;
+	TEST_RES(get_next_key(fd, &key, &key), key == 3);
+	TEST_ERRNO(get_next_key(fd, &key, &key), ENOENT);
+	key = 100;
+	TEST_RES(get_next_key(fd, &key, &key), key == 0);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(hash_map)
+{
+	unsigned int key, next_key;
+	unsigned int value;
+	int fd;
+
+	TEST_ERRNO(create_map(BPF_MAP_TYPE_HASH, 0, 4, 2), EINVAL);
+	fd = TEST_SUCC(create_map(BPF_MAP_TYPE_HASH, 4, 4, 2));
+
+	key = 1;
+	value = 10;
+	TEST_ERRNO(lookup_elem(fd, &key, &value), ENOENT);
+	TEST_ERRNO(update_elem(fd, &key, &value, BPF_EXIST), ENOENT);
+	TEST_SUCC(update_elem(fd, &key, &value, BPF_NOEXIST));
+	TEST_ERRNO(update_elem(fd, &key, &value, BPF_NOEXIST), EEXIST);
+
+	key = 2;
+	value = 20;
+	TEST_SUCC(update_elem(fd, &key, &value, BPF_ANY));
+	key = 3;
+	TEST_ERRNO(update_elem(fd, &key, &value, BPF_ANY), E2BIG);
+	TEST_ERRNO(delete_elem(fd, &key), ENOENT);
+
+	key = 2;
+	value = 0;
+	TEST_RES(lookup_elem(fd, &key, &value), value == 20);
+
+	// The keys can be iterated over in an unspecified order.
+	TEST_RES(get_next_key(fd, NULL, &key), key == 1 || key == 2);
+	TEST_RES(get_next_key(fd, &key, &next_key),
+		 next_key == 3 - key);
+	TEST_ERRNO(get_next_key(fd, &next_key, &key), ENOENT);
+
+	key = 1;
+	TEST_SUCC(delete_elem(fd, &key));
+	TEST_ERRNO(lookup_elem(fd, &key, &value), ENOENT);
+	TEST_RES(get_next_key(fd, NULL, &key), key == 2);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(ringbuf_map)
+{
+	TEST_ERRNO(create_map(BPF_MAP_TYPE_RINGBUF, 4, 0, RINGBUF_SIZE),
+		   EINVAL);
+	TEST_ERRNO(create_map(BPF_MAP_TYPE_RINGBUF, 0, 0, RINGBUF_SIZE + 1),
+		   EINVAL);
+	TEST_SUCC(close(TEST_SUCC(
+		create_map(BPF_MAP_TYPE_RINGBUF, 0, 0, RINGBUF_SIZE))));
+}
+END_TEST()
+
+FN_TEST(prog_load)
+{
+	int map_fd, sk;
+
+	map_fd = TEST_SUCC(create_map(BPF_MAP_TYPE_ARRAY, 4, 8, 1));
+	sk = TEST_SUCC(socket(AF_INET, SOCK_DGRAM, 0));
+
+	TEST_ERRNO(load_prog(NULL, 0), EINVAL);
+
+	// The program must end with an exit.
+	TEST_ERRNO(LOAD_PROG(INSN(BPF_ALU64 | BPF_MOV | BPF_K, 0, 0, 0, 0)),
+		   EINVAL);
+
+	// The frame pointer is read-only.
+	TEST_ERRNO(LOAD_PROG(INSN(BPF_ALU64 | BPF_MOV | BPF_K, 10, 0, 0, 0),
+			     INSN_EXIT()),
+		   EACCES);
+
+	// The jump target is out of bounds.
+	TEST_ERRNO(LOAD_PROG(INSN(BPF_ALU64 | BPF_MOV | BPF_K, 0, 0, 0, 0),
+			     INSN(BPF_JMP | BPF_JA, 0, 0, 5, 0), INSN_EXIT()),
+		   EINVAL);
+
+	// The program divides by zero.
+	TEST_ERRNO(LOAD_PROG(INSN(BPF_ALU64 | BPF_MOV | BPF_K, 0, 0, 0, 1),
+			     INSN(BPF_ALU64 | BPF_DIV | BPF_K, 0, 0, 0, 0),
+			     INSN_EXIT()),
+		   EINVAL);
+
+	// The helper does not exist.
+	TEST_ERRNO(LOAD_PROG(INSN(BPF_JMP | BPF_CALL, 0, 0, 0, 100000),
+			     INSN_EXIT()),
+		   EINVAL);
+
+	// The map file descriptor is invalid.
+	TEST_ERRNO(LOAD_PROG(INSN_LD_MAP_FD(0, sk), INSN_EXIT()), EINVAL);
+	TEST_ERRNO(LOAD_PROG(INSN_LD_MAP_FD(0, 1000), INSN_EXIT()), EBADF);
+
+	TEST_SUCC(close(TEST_SUCC(
+		LOAD_PROG(INSN_LD_MAP_FD(0, map_fd), INSN_EXIT()))));
+
+	TEST_SUCC(close(sk));
+	TEST_SUCC(close(map_fd));
+}
+END_TEST()
+
+struct test_frame {
+	struct ether_header eth;
+	struct iphdr ip;
+	char payload[PAYLOAD_LEN];
+} __attribute__((packed));
+
+static void fill_frame(struct test_frame *frame)
+{
+	memset(frame, 0, sizeof(*frame));
+
+	frame->eth.ether_type = htons(ETH_P_IP);
+
+	frame->ip.version = 4;
+	frame->ip.ihl = 5;
+	frame->ip.tot_len = htons(sizeof(frame->ip) + PAYLOAD_LEN);
+	frame->ip.ttl = 64;
+	frame->ip.protocol = TEST_PROTO;
+	frame->ip.saddr = htonl(INADDR_LOOPBACK);
+	frame->ip.daddr = htonl(INADDR_LOOPBACK);
+
+	memcpy(frame->payload, PAYLOAD, PAYLOAD_LEN);
+}
+
+// Accepts incoming IPv4 packets of `TEST_PROTO`, counts them in the array map,
+// and outputs their lengths to the ring buffer.
+static int load_filter(int array_fd, int ringbuf_fd)
+{
+	return LOAD_PROG(
+		// r6 = ctx
+		INSN(BPF_ALU64 | BPF_MOV | BPF_X, 6, 1, 0, 0),
+		// r0 = the IPv4 protocol
+		INSN(BPF_LD | BPF_ABS | BPF_B, 0, 0, 0, 9),
+		// if r0 != TEST_PROTO goto drop
+		INSN(BPF_JMP | BPF_JNE | BPF_K, 0, 0, 22, TEST_PROTO),
+		// if ctx->pkt_type != PACKET_HOST goto drop
+		INSN(BPF_LDX | BPF_MEM | BPF_W, 2, 6, 0,
+		     offsetof(struct __sk_buff, pkt_type)),
+		INSN(BPF_JMP | BPF_JNE | BPF_K, 2, 0, 20, PACKET_HOST),
+		// r0 = bpf_map_lookup_elem(array, &(u32){ 0 })
+		INSN(BPF_ST | BPF_MEM | BPF_W, 10, 0, -4, 0),
+		INSN(BPF_ALU64 | BPF_MOV | BPF_X, 2, 10, 0, 0),
+		INSN(BPF_ALU64 | BPF_ADD | BPF_K, 2, 0, 0, -4),
+		INSN_LD_MAP_FD(1, array_fd),
+		INSN(BPF_JMP | BPF_CALL, 0, 0, 0, BPF_FUNC_map_lookup_elem),
+		// if r0 == NULL goto drop
+		INSN(BPF_JMP | BPF_JEQ | BPF_K, 0, 0, 13, 0),
+		// *(u64 *)r0 += 1 atomically
+		INSN(BPF_ALU64 | BPF_MOV | BPF_K, 1, 0, 0, 1),
+		INSN(BPF_STX | BPF_ATOMIC | BPF_DW, 0, 1, 0, BPF_ADD),
+		// bpf_ringbuf_output(ringbuf, &(u64){ ctx->len }, 8, 0)
+		INSN(BPF_LDX | BPF_MEM | BPF_W, 1, 6, 0,
+		     offsetof(struct __sk_buff, len)),
+		INSN(BPF_STX | BPF_MEM | BPF_DW, 10, 1, -16, 0),
+		INSN_LD_MAP_FD(1, ringbuf_fd),
+		INSN(BPF_ALU64 | BPF_MOV | BPF_X, 2, 10, 0, 0),
+		INSN(BPF_ALU64 | BPF_ADD | BPF_K, 2, 0, 0, -16),
+		INSN(BPF_ALU64 | BPF_MOV | BPF_K, 3, 0, 0, 8),
+		INSN(BPF_ALU64 | BPF_MOV | BPF_K, 4, 0, 0, 0),
+		INSN(BPF_JMP | BPF_CALL, 0, 0, 0, BPF_FUNC_ringbuf_output),
+		// return 0xffff
+		INSN(BPF_ALU64 | BPF_MOV | BPF_K, 0, 0, 0, 0xffff),
+		INSN_EXIT(),
+		// drop: return 0
+		INSN(BPF_ALU64 | BPF_MOV | BPF_K, 0, 0, 0, 0), INSN_EXIT());
+}
+
+FN_TEST(socket_filter)
+{
+	struct sockaddr_ll addr = {
+		.sll_family = AF_PACKET,
+		.sll_protocol = htons(ETH_P_IP),
+		.sll_ifindex = LO_IFINDEX,
+	};
+	struct test_frame frame;
+	struct iphdr recv_ip;
+	struct pollfd pfd;
+	unsigned long *consumer, count;
+	char *producer;
+	unsigned int key = 0;
+	int array_fd, ringbuf_fd, prog_fd, sk_send, sk_recv;
+	long page_size = sysconf(_SC_PAGESIZE);
+
+	array_fd = TEST_SUCC(create_map(BPF_MAP_TYPE_ARRAY, 4, 8, 1));
+	ringbuf_fd =
+		TEST_SUCC(create_map(BPF_MAP_TYPE_RINGBUF, 0, 0, RINGBUF_SIZE));
+	prog_fd = TEST_SUCC(load_filter(array_fd, ringbuf_fd));
+
+	sk_send = TEST_SUCC(socket(AF_PACKET, SOCK_RAW, htons(ETH_P_IP)));
+	sk_recv = TEST_SUCC(socket(AF_PACKET, SOCK_DGRAM, htons(ETH_P_IP)));
+	TEST_SUCC(bind(sk_send, (struct sockaddr *)&addr, sizeof(addr)));
+	TEST_SUCC(bind(sk_recv, (struct sockaddr *)&addr, sizeof(addr)));
+
+	TEST_ERRNO(setsockopt(sk_recv, SOL_SOCKET, SO_ATTACH_BPF, &array_fd,
+			      sizeof(array_fd)),
+		   EINVAL);
+	TEST_SUCC(setsockopt(sk_recv, SOL_SOCKET, SO_ATTACH_BPF, &prog_fd,
+			     sizeof(prog_fd)));
+
+	consumer = TEST_SUCC(mmap(NULL, page_size, PROT_READ | PROT_WRITE,
+				  MAP_SHARED, ringbuf_fd, 0));
+	producer = TEST_SUCC(mmap(NULL, page_size + 2 * RINGBUF_SIZE,
+				  PROT_READ, MAP_SHARED, ringbuf_fd,
+				  page_size));
+
+	pfd.fd = ringbuf_fd;
+	pfd.events = POLLIN;
+	TEST_RES(poll(&pfd, 1, 0), _ret == 0);
+
+	fill_frame(&frame);
+	TEST_RES(send(sk_send, &frame, sizeof(frame), 0),
+		 _ret == sizeof(frame));
+
+	// The outgoing packet is dropped and the incoming packet is accepted.
+	TEST_RES(recv(sk_recv, &recv_ip, sizeof(recv_ip), MSG_DONTWAIT),
+		 _ret == sizeof(recv_ip) && recv_ip.protocol == TEST_PROTO);
+	TEST_ERRNO(recv(sk_recv, &recv_ip, sizeof(recv_ip), MSG_DONTWAIT),
+		   EAGAIN);
+	TEST_RES(lookup_elem(array_fd, &key, &count), count == 1);
+
+	// The ring buffer contains a record with the packet length.
+	TEST_RES(poll(&pfd, 1, 0), _ret == 1 && pfd.revents == POLLIN);
+	TEST_RES(*(unsigned long *)producer, _ret == 16);
+	TEST_RES(*(unsigned int *)(producer + page_size), _ret == 8);
+	TEST_RES(*(unsigned long *)(producer + page_size + 8),
+		 _ret == sizeof(frame) - sizeof(frame.eth));
+
+	*consumer = 16;
+	TEST_RES(poll(&pfd, 1, 0), _ret == 0);
+
+	TEST_SUCC(munmap(producer, page_size + 2 * RINGBUF_SIZE));
+	TEST_SUCC(munmap(consumer, page_size));
+	TEST_SUCC(close(sk_recv));
+	TEST_SUCC(close(sk_send));
+	TEST_SUCC(close(prog_fd));
+	TEST_SUCC(close(ringbuf_fd));
+	TEST_SUCC(close(array_fd));
+}
+END_TEST()
@@ -0,0 +1,149 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <unistd.h>
+#include <string.h>
+#include <net/if.h>
+#include <net/if_arp.h>
+#include <sys/ioctl.h>
+#include <sys/socket.h>
+#include <netinet/in.h>
+#include <arpa/inet.h>
+#include <linux/ethtool.h>
+#include <linux/sockios.h>
+
+#include "../common/test.h"
+
+static int sk;
+static struct ifreq ifr;
+
+FN_SETUP(init)
+{
+	sk = CHECK(socket(AF_INET, SOCK_DGRAM, 0));
+}
+END_SETUP()
+
+static void init_ifreq(const char *name)
+{
+	memset(&ifr, 0, sizeof(ifr));
+	strncpy(ifr.ifr_name, name, IFNAMSIZ - 1);
+}
+
+static in_addr_t ifreq_addr_of(struct ifreq *req)
+{
+	return ((struct sockaddr_in *)&req->ifr_addr)->sin_addr.s_addr;
+}
+
+static in_addr_t ifreq_addr(void)
+{
+	return ifreq_addr_of(&ifr);
+}
+
+FN_TEST(index_and_name)
+{
+	int index;
+
+	init_ifreq("lo");
+	TEST_RES(ioctl(sk, SIOCGIFINDEX, &ifr),
+		 ifr.ifr_ifindex == (int)if_nametoindex("lo"));
+	index = ifr.ifr_ifindex;
+
+	init_ifreq("");
+	ifr.ifr_ifindex = index;
+	TEST_RES(ioctl(sk, SIOCGIFNAME, &ifr), strcmp(ifr.ifr_name, "lo") == 0);
+
+	ifr.ifr_ifindex = 12345;
+	TEST_ERRNO(ioctl(sk, SIOCGIFNAME, &ifr), ENODEV);
+
+	init_ifreq("none");
+	TEST_ERRNO(ioctl(sk, SIOCGIFINDEX, &ifr), ENODEV);
+}
+END_TEST()
+
+FN_TEST(flags_and_mtu)
+{
+	short flags = IFF_UP | IFF_LOOPBACK | IFF_RUNNING;
+
+	init_ifreq("lo");
+	TEST_RES(ioctl(sk, SIOCGIFFLAGS, &ifr),
+		 (ifr.ifr_flags & flags) == flags);
+
+	init_ifreq("lo");
+	TEST_RES(ioctl(sk, SIOCGIFMTU, &ifr), ifr.ifr_mtu > 0);
+
+	init_ifreq("lo");
+	TEST_RES(ioctl(sk, SIOCGIFHWADDR, &ifr),
+		 ifr.ifr_hwaddr.sa_family == ARPHRD_LOOPBACK);
+}
+END_TEST()
+
+FN_TEST(addr_and_netmask)
+{
+	init_ifreq("lo");
+	TEST_RES(ioctl(sk, SIOCGIFADDR, &ifr),
+		 ifr.ifr_addr.sa_family == AF_INET &&
+			 ifreq_addr() == htonl(INADDR_LOOPBACK));
+
+	init_ifreq("lo");
+	TEST_RES(ioctl(sk, SIOCGIFNETMASK, &ifr),
+		 ifr.ifr_netmask.sa_family == AF_INET &&
+			 ifreq_addr() == htonl(0xff000000));
+
+	// Setting the same address is a no-op.
+	init_ifreq("lo");
+	TEST_SUCC(ioctl(sk, SIOCGIFADDR, &ifr));
+	TEST_SUCC(ioctl(sk, SIOCSIFADDR, &ifr));
+
+	ifr.ifr_addr.sa_family = AF_INET6;
+	TEST_ERRNO(ioctl(sk, SIOCSIFADDR, &ifr), EINVAL);
+}
+END_TEST()
+
+FN_TEST(iface_conf)
+{
+	struct ifreq ifrs[16];
+	struct ifconf ifc;
+	int len, count, i;
+
+	ifc.ifc_len = 0;
+	ifc.ifc_req = NULL;
+	TEST_RES(ioctl(sk, SIOCGIFCONF, &ifc),
+		 ifc.ifc_len > 0 && ifc.ifc_len % sizeof(struct ifreq) == 0);
+	len = ifc.ifc_len;
+
+	ifc.ifc_len = sizeof(struct ifreq) - 1;
+	ifc.ifc_req = ifrs;
+	TEST_RES(ioctl(sk, SIOCGIFCONF, &ifc), ifc.ifc_len == 0);
+
+	ifc.ifc_len = sizeof(ifrs);
+	TEST_RES(ioctl(sk, SIOCGIFCONF, &ifc), ifc.ifc_len == len);
+
+	count = len / sizeof(struct ifreq);
+	for (i = 0; i < count; ++i)
+		if (strcmp(ifrs[i].ifr_name, "lo") == 0)
+			break;
+	TEST_RES(i, i < count &&
+			    ifreq_addr_of(&ifrs[i]) == htonl(INADDR_LOOPBACK));
+}
+END_TEST()
+
+FN_TEST(ethtool)
+{
+	struct ethtool_value value = { .cmd = ETHTOOL_GLINK };
+	struct ethtool_cmd settings = { .cmd = ETHTOOL_GSET };
+
+	init_ifreq("lo");
+	ifr.ifr_data = (void *)&value;
+	TEST_RES(ioctl(sk, SIOCETHTOOL, &ifr), value.data == 1);
+
+	ifr.ifr_data = (void *)&settings;
+	TEST_ERRNO(ioctl(sk, SIOCETHTOOL, &ifr), EOPNOTSUPP);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(sk));
+}
+END_SETUP()
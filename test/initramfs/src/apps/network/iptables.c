@@ -0,0 +1,438 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+#include <string.h>
+#include <unistd.h>
+#include <sys/socket.h>
+#include <netinet/in.h>
+#include <arpa/inet.h>
+#include <linux/netfilter_ipv4/ip_tables.h>
+#include <linux/netfilter/xt_tcpudp.h>
+#include <linux/netfilter/nf_nat.h>
+
+#include "../common/test.h"
+
+#define FILTER_VALID_HOOKS                                \
+	((1 << NF_INET_LOCAL_IN) | (1 << NF_INET_FORWARD) | \
+	 (1 << NF_INET_LOCAL_OUT))
+#define NAT_VALID_HOOKS                                          \
+	((1 << NF_INET_PRE_ROUTING) | (1 << NF_INET_LOCAL_IN) | \
+	 (1 << NF_INET_LOCAL_OUT) | (1 << NF_INET_POST_ROUTING))
+
+#define DROPPED_PORT 23456
+#define ACCEPTED_PORT 23457
+#define DNAT_FROM_PORT 23458
+#define DNAT_TO_PORT 23459
+
+#define STANDARD_ENTRY_SIZE \
+	(sizeof(struct ipt_entry) + XT_ALIGN(sizeof(struct xt_standard_target)))
+#define UDP_ENTRY_SIZE                                         \
+	(sizeof(struct ipt_entry) +                            \
+	 XT_ALIGN(sizeof(struct xt_entry_match) +              \
+		  sizeof(struct xt_udp)) +                     \
+	 XT_ALIGN(sizeof(struct xt_standard_target)))
+#define DNAT_ENTRY_SIZE                                        \
+	(sizeof(struct ipt_entry) +                            \
+	 XT_ALIGN(sizeof(struct xt_entry_match) +              \
+		  sizeof(struct xt_udp)) +                     \
+	 XT_ALIGN(sizeof(struct xt_entry_target) +             \
+		  sizeof(struct nf_nat_ipv4_multi_range_compat)))
+#define ERROR_ENTRY_SIZE \
+	(sizeof(struct ipt_entry) + XT_ALIGN(sizeof(struct xt_error_target)))
+
+static int sk_raw;
+static struct ipt_getinfo info;
+
+FN_SETUP(get_info)
+{
+	socklen_t len = sizeof(info);
+
+	sk_raw = CHECK(socket(AF_INET, SOCK_RAW, IPPROTO_RAW));
+
+	strcpy(info.name, "filter");
+	CHECK(getsockopt(sk_raw, SOL_IP, IPT_SO_GET_INFO, &info, &len));
+}
+END_SETUP()
+
+FN_TEST(default_table)
+{
+	struct {
+		struct ipt_get_entries header;
+		char entries[4096];
+	} get_entries;
+	struct ipt_getinfo other_info;
+	struct xt_standard_target *target;
+	struct ipt_entry *entry;
+	socklen_t len;
+
+	TEST_RES(info.valid_hooks, _ret == FILTER_VALID_HOOKS);
+	TEST_RES(info.num_entries, _ret == 4);
+	TEST_RES(info.size,
+		 _ret == 3 * STANDARD_ENTRY_SIZE + ERROR_ENTRY_SIZE);
+	TEST_RES(info.hook_entry[NF_INET_LOCAL_IN], _ret == 0);
+	TEST_RES(info.underflow[NF_INET_LOCAL_OUT],
+		 _ret == 2 * STANDARD_ENTRY_SIZE);
+
+	memset(&other_info, 0, sizeof(other_info));
+	strcpy(other_info.name, "nat");
+	len = sizeof(other_info);
+	TEST_SUCC(getsockopt(sk_raw, SOL_IP, IPT_SO_GET_INFO, &other_info,
+			     &len));
+	TEST_RES(other_info.valid_hooks, _ret == NAT_VALID_HOOKS);
+	TEST_RES(other_info.num_entries, _ret == 5);
+	TEST_RES(other_info.size,
+		 _ret == 4 * STANDARD_ENTRY_SIZE + ERROR_ENTRY_SIZE);
+
+	strcpy(other_info.name, "mangle");
+	TEST_ERRNO(getsockopt(sk_raw, SOL_IP, IPT_SO_GET_INFO, &other_info,
+			      &len),
+		   ENOENT);
+	len = sizeof(other_info) - 1;
+	TEST_ERRNO(getsockopt(sk_raw, SOL_IP, IPT_SO_GET_INFO, &other_info,
+			      &len),
+		   EINVAL);
+
+	memset(&get_entries, 0, sizeof(get_entries));
+	strcpy(get_entries.header.name, "filter");
+	get_entries.header.size = info.size - 1;
+	len = sizeof(get_entries.header) + info.size - 1;
+	TEST_ERRNO(getsockopt(sk_raw, SOL_IP, IPT_SO_GET_ENTRIES,
+			      &get_entries, &len),
+		   EAGAIN);
+
+	get_entries.header.size = info.size;
+	len = sizeof(get_entries.header) + info.size;
+	TEST_SUCC(getsockopt(sk_raw, SOL_IP, IPT_SO_GET_ENTRIES, &get_entries,
+			     &len));
+
+	entry = get_entries.header.entrytable;
+	target = (void *)entry + entry->target_offset;
+	TEST_RES(entry->next_offset, _ret == STANDARD_ENTRY_SIZE);
+	TEST_RES(strcmp(target->target.u.user.name, XT_STANDARD_TARGET),
+		 _ret == 0);
+	TEST_RES(target->verdict, _ret == -NF_ACCEPT - 1);
+}
+END_TEST()
+
+FN_TEST(get_revision)
+{
+	struct xt_get_revision rev;
+	socklen_t len = sizeof(rev);
+
+	memset(&rev, 0, sizeof(rev));
+	strcpy(rev.name, "udp");
+	TEST_SUCC(getsockopt(sk_raw, SOL_IP, IPT_SO_GET_REVISION_MATCH, &rev,
+			     &len));
+
+	rev.revision = 1;
+	TEST_ERRNO(getsockopt(sk_raw, SOL_IP, IPT_SO_GET_REVISION_MATCH, &rev,
+			      &len),
+		   EPROTONOSUPPORT);
+
+	strcpy(rev.name, "DNAT");
+	TEST_SUCC(getsockopt(sk_raw, SOL_IP, IPT_SO_GET_REVISION_TARGET, &rev,
+			     &len));
+
+	strcpy(rev.name, "nonexistent");
+	rev.revision = 0;
+	TEST_ERRNO(getsockopt(sk_raw, SOL_IP, IPT_SO_GET_REVISION_TARGET, &rev,
+			      &len),
+		   ENOENT);
+}
+END_TEST()
+
+static void *append_standard_entry(void *entry, int verdict)
+{
+	struct ipt_entry *header = entry;
+	struct xt_standard_target *target;
+
+	header->target_offset = sizeof(struct ipt_entry);
+	header->next_offset = STANDARD_ENTRY_SIZE;
+
+	target = entry + header->target_offset;
+	target->target.u.user.target_size =
+		XT_ALIGN(sizeof(struct xt_standard_target));
+	target->verdict = verdict;
+
+	return entry + header->next_offset;
+}
+
+static void *append_udp_drop_entry(void *entry, unsigned short port)
+{
+	struct ipt_entry *header = entry;
+	struct xt_entry_match *match;
+	struct xt_udp *udp;
+	struct xt_standard_target *target;
+
+	header->ip.proto = IPPROTO_UDP;
+	header->target_offset =
+		sizeof(struct ipt_entry) +
+		XT_ALIGN(sizeof(struct xt_entry_match) + sizeof(struct xt_udp));
+	header->next_offset = UDP_ENTRY_SIZE;
+
+	match = entry + sizeof(struct ipt_entry);
+	match->u.user.match_size = header->target_offset -
+				   sizeof(struct ipt_entry);
+	strcpy(match->u.user.name, "udp");
+
+	udp = (struct xt_udp *)match->data;
+	udp->spts[1] = 0xFFFF;
+	udp->dpts[0] = port;
+	udp->dpts[1] = port;
+
+	target = entry + header->target_offset;
+	target->target.u.user.target_size =
+		XT_ALIGN(sizeof(struct xt_standard_target));
+	target->verdict = -NF_DROP - 1;
+
+	return entry + header->next_offset;
+}
+
+static void *append_error_entry(void *entry)
+{
+	struct ipt_entry *header = entry;
+	struct xt_error_target *target;
+
+	header->target_offset = sizeof(struct ipt_entry);
+	header->next_offset = ERROR_ENTRY_SIZE;
+
+	target = entry + header->target_offset;
+	target->target.u.user.target_size =
+		XT_ALIGN(sizeof(struct xt_error_target));
+	strcpy(target->target.u.user.name, XT_ERROR_TARGET);
+	strcpy(target->errorname, "ERROR");
+
+	return entry + header->next_offset;
+}
+
+static struct {
+	struct ipt_replace header;
+	char entries[4096];
+} replace;
+static struct xt_counters counters[16];
+
+static unsigned int entry_offset(void *entry)
+{
+	return (char *)entry - replace.entries;
+}
+
+// Builds a table whose `INPUT` chain drops the UDP packets to `port`, or a
+// table that accepts all packets if `port` is zero.
+static socklen_t build_table(unsigned short port, unsigned int num_counters)
+{
+	void *entry = replace.entries;
+
+	memset(&replace, 0, sizeof(replace));
+	strcpy(replace.header.name, "filter");
+	replace.header.valid_hooks = FILTER_VALID_HOOKS;
+	replace.header.num_counters = num_counters;
+	replace.header.counters = counters;
+
+	replace.header.hook_entry[NF_INET_LOCAL_IN] = 0;
+	if (port != 0)
+		entry = append_udp_drop_entry(entry, port);
+	replace.header.underflow[NF_INET_LOCAL_IN] = entry_offset(entry);
+	entry = append_standard_entry(entry, -NF_ACCEPT - 1);
+
+	replace.header.hook_entry[NF_INET_FORWARD] = entry_offset(entry);
+	replace.header.underflow[NF_INET_FORWARD] = entry_offset(entry);
+	entry = append_standard_entry(entry, -NF_ACCEPT - 1);
+
+	replace.header.hook_entry[NF_INET_LOCAL_OUT] = entry_offset(entry);
+	replace.header.underflow[NF_INET_LOCAL_OUT] = entry_offset(entry);
+	entry = append_standard_entry(entry, -NF_ACCEPT - 1);
+
+	entry = append_error_entry(entry);
+
+	replace.header.size = entry_offset(entry);
+	replace.header.num_entries = port != 0 ? 5 : 4;
+
+	return sizeof(replace.header) + replace.header.size;
+}
+
+static int send_and_recv(int sk, unsigned short port)
+{
+	struct sockaddr_in addr = {
+		.sin_family = AF_INET,
+		.sin_port = htons(port),
+		.sin_addr = { .s_addr = htonl(INADDR_LOOPBACK) },
+	};
+	char buf[2];
+
+	if (bind(sk, (struct sockaddr *)&addr, sizeof(addr)) < 0)
+		return -1;
+	if (sendto(sk, "x", 1, 0, (struct sockaddr *)&addr, sizeof(addr)) < 0)
+		return -1;
+	return recv(sk, buf, sizeof(buf), MSG_DONTWAIT);
+}
+
+FN_TEST(replace_and_filter)
+{
+	socklen_t len;
+	int sk_dropped, sk_accepted;
+
+	len = build_table(DROPPED_PORT, info.num_entries + 1);
+	TEST_ERRNO(setsockopt(sk_raw, SOL_IP, IPT_SO_SET_REPLACE, &replace,
+			      len),
+		   EAGAIN);
+
+	len = build_table(DROPPED_PORT, info.num_entries);
+	replace.header.valid_hooks = 1 << NF_INET_LOCAL_IN;
+	TEST_ERRNO(setsockopt(sk_raw, SOL_IP, IPT_SO_SET_REPLACE, &replace,
+			      len),
+		   EINVAL);
+
+	len = build_table(DROPPED_PORT, info.num_entries);
+	TEST_ERRNO(setsockopt(sk_raw, SOL_IP, IPT_SO_SET_REPLACE, &replace,
+			      len - 1),
+		   ENOPROTOOPT);
+	TEST_SUCC(setsockopt(sk_raw, SOL_IP, IPT_SO_SET_REPLACE, &replace,
+			     len));
+
+	sk_dropped = TEST_SUCC(socket(AF_INET, SOCK_DGRAM, 0));
+	sk_accepted = TEST_SUCC(socket(AF_INET, SOCK_DGRAM, 0));
+	TEST_ERRNO(send_and_recv(sk_dropped, DROPPED_PORT), EAGAIN);
+	TEST_RES(send_and_recv(sk_accepted, ACCEPTED_PORT), _ret == 1);
+
+	// Restore the default table.
+	len = build_table(0, 5);
+	TEST_SUCC(setsockopt(sk_raw, SOL_IP, IPT_SO_SET_REPLACE, &replace,
+			     len));
+
+	TEST_SUCC(close(sk_dropped));
+	TEST_SUCC(close(sk_accepted));
+}
+END_TEST()
+
+static void *append_dnat_entry(void *entry, unsigned short port,
+			       unsigned short to_port)
+{
+	struct ipt_entry *header = entry;
+	struct xt_entry_match *match;
+	struct xt_udp *udp;
+	struct xt_entry_target *target;
+	struct nf_nat_ipv4_multi_range_compat *range;
+
+	header->ip.proto = IPPROTO_UDP;
+	header->target_offset =
+		sizeof(struct ipt_entry) +
+		XT_ALIGN(sizeof(struct xt_entry_match) + sizeof(struct xt_udp));
+	header->next_offset = DNAT_ENTRY_SIZE;
+
+	match = entry + sizeof(struct ipt_entry);
+	match->u.user.match_size = header->target_offset -
+				   sizeof(struct ipt_entry);
+	strcpy(match->u.user.name, "udp");
+
+	udp = (struct xt_udp *)match->data;
+	udp->spts[1] = 0xFFFF;
+	udp->dpts[0] = port;
+	udp->dpts[1] = port;
+
+	target = entry + header->target_offset;
+	target->u.user.target_size = header->next_offset -
+				     header->target_offset;
+	strcpy(target->u.user.name, "DNAT");
+
+	range = (struct nf_nat_ipv4_multi_range_compat *)target->data;
+	range->rangesize = 1;
+	range->range[0].flags = NF_NAT_RANGE_PROTO_SPECIFIED;
+	range->range[0].min.udp.port = htons(to_port);
+	range->range[0].max.udp.port = htons(to_port);
+
+	return entry + header->next_offset;
+}
+
+// Builds a `nat` table whose `OUTPUT` chain redirects the UDP packets to
+// `port` to `to_port`, or a table that translates no packets if `port` is zero.
+static socklen_t build_nat_table(unsigned short port, unsigned short to_port,
+				 unsigned int num_counters)
+{
+	void *entry = replace.entries;
+
+	memset(&replace, 0, sizeof(replace));
+	strcpy(replace.header.name, "nat");
+	replace.header.valid_hooks = NAT_VALID_HOOKS;
+	replace.header.num_counters = num_counters;
+	replace.header.counters = counters;
+
+	replace.header.hook_entry[NF_INET_PRE_ROUTING] = entry_offset(entry);
+	replace.header.underflow[NF_INET_PRE_ROUTING] = entry_offset(entry);
+	entry = append_standard_entry(entry, -NF_ACCEPT - 1);
+
+	replace.header.hook_entry[NF_INET_LOCAL_IN] = entry_offset(entry);
+	replace.header.underflow[NF_INET_LOCAL_IN] = entry_offset(entry);
+	entry = append_standard_entry(entry, -NF_ACCEPT - 1);
+
+	replace.header.hook_entry[NF_INET_LOCAL_OUT] = entry_offset(entry);
+	if (port != 0)
+		entry = append_dnat_entry(entry, port, to_port);
+	replace.header.underflow[NF_INET_LOCAL_OUT] = entry_offset(entry);
+	entry = append_standard_entry(entry, -NF_ACCEPT - 1);
+
+	replace.header.hook_entry[NF_INET_POST_ROUTING] = entry_offset(entry);
+	replace.header.underflow[NF_INET_POST_ROUTING] = entry_offset(entry);
+	entry = append_standard_entry(entry, -NF_ACCEPT - 1);
+
+	entry = append_error_entry(entry);
+
+	replace.header.size = entry_offset(entry);
+	replace.header.num_entries = port != 0 ? 6 : 5;
+
+	return sizeof(replace.header) + replace.header.size;
+}
+
+FN_TEST(dnat)
+{
+	struct sockaddr_in addr = {
+		.sin_family = AF_INET,
+		.sin_addr = { .s_addr = htonl(INADDR_LOOPBACK) },
+	};
+	socklen_t len, addrlen;
+	int sk_client, sk_server;
+	char buf[2];
+
+	len = build_nat_table(DNAT_FROM_PORT, DNAT_TO_PORT, 5);
+	TEST_SUCC(setsockopt(sk_raw, SOL_IP, IPT_SO_SET_REPLACE, &replace,
+			     len));
+
+	sk_client = TEST_SUCC(socket(AF_INET, SOCK_DGRAM, 0));
+	sk_server = TEST_SUCC(socket(AF_INET, SOCK_DGRAM, 0));
+
+	addr.sin_port = htons(DNAT_TO_PORT);
+	TEST_SUCC(bind(sk_server, (struct sockaddr *)&addr, sizeof(addr)));
+
+	// The request is redirected to the server.
+	addr.sin_port = htons(DNAT_FROM_PORT);
+	TEST_RES(sendto(sk_client, "x", 1, 0, (struct sockaddr *)&addr,
+			sizeof(addr)),
+		 _ret == 1);
+	addrlen = sizeof(addr);
+	TEST_RES(recvfrom(sk_server, buf, sizeof(buf), MSG_DONTWAIT,
+			  (struct sockaddr *)&addr, &addrlen),
+		 _ret == 1);
+
+	// The reply seems to come from the original destination.
+	TEST_RES(sendto(sk_server, "y", 1, 0, (struct sockaddr *)&addr,
+			sizeof(addr)),
+		 _ret == 1);
+	addrlen = sizeof(addr);
+	TEST_RES(recvfrom(sk_client, buf, sizeof(buf), MSG_DONTWAIT,
+			  (struct sockaddr *)&addr, &addrlen),
+		 _ret == 1 && addr.sin_port == htons(DNAT_FROM_PORT));
+
+	// Restore the default table.
+	len = build_nat_table(0, 0, 6);
+	TEST_SUCC(setsockopt(sk_raw, SOL_IP, IPT_SO_SET_REPLACE, &replace,
+			     len));
+
+	TEST_SUCC(close(sk_client));
+	TEST_SUCC(close(sk_server));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(sk_raw));
+}
+END_SETUP()
@@ -0,0 +1,182 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#include <unistd.h>
+#include <sys/socket.h>
+#include <netinet/in.h>
+#include <arpa/inet.h>
+#include <string.h>
+
+#include "../common/test.h"
+
+#define C_PORT htons(0x1235)
+#define S_PORT htons(0x1236)
+
+static struct sockaddr_in6 sk_addr6;
+static struct sockaddr_in6 sk_mapped_addr6;
+static struct sockaddr_in sk_addr4;
+
+FN_SETUP(general)
+{
+	sk_addr6.sin6_family = AF_INET6;
+	sk_addr6.sin6_addr = in6addr_loopback;
+
+	sk_mapped_addr6.sin6_family = AF_INET6;
+	CHECK_WITH(inet_pton(AF_INET6, "::ffff:127.0.0.1",
+			     &sk_mapped_addr6.sin6_addr),
+		   _ret == 1);
+
+	sk_addr4.sin_family = AF_INET;
+	CHECK(inet_aton("127.0.0.1", &sk_addr4.sin_addr));
+}
+END_SETUP()
+
+FN_TEST(getsockname_unbound)
+{
+	struct sockaddr_in6 saddr = { .sin6_port = 0xbeef };
+	socklen_t addrlen = sizeof(saddr);
+	int sk = TEST_SUCC(socket(PF_INET6, SOCK_DGRAM, 0));
+
+	TEST_RES(getsockname(sk, (struct sockaddr *)&saddr, &addrlen),
+		 addrlen == sizeof(saddr) && saddr.sin6_family == AF_INET6 &&
+			 saddr.sin6_port == 0 &&
+			 IN6_IS_ADDR_UNSPECIFIED(&saddr.sin6_addr));
+
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(v6only)
+{
+	int enable = 1;
+	int value = 0xbeef;
+	socklen_t len = sizeof(value);
+	int sk4 = TEST_SUCC(socket(PF_INET, SOCK_DGRAM, 0));
+	int sk6 = TEST_SUCC(socket(PF_INET6, SOCK_DGRAM, 0));
+
+	TEST_ERRNO(setsockopt(sk4, SOL_IPV6, IPV6_V6ONLY, &enable,
+			      sizeof(enable)),
+		   ENOPROTOOPT);
+
+	TEST_RES(getsockopt(sk6, SOL_IPV6, IPV6_V6ONLY, &value, &len),
+		 len == sizeof(value) && value == 0);
+	TEST_SUCC(setsockopt(sk6, SOL_IPV6, IPV6_V6ONLY, &enable,
+			     sizeof(enable)));
+	TEST_RES(getsockopt(sk6, SOL_IPV6, IPV6_V6ONLY, &value, &len),
+		 len == sizeof(value) && value == 1);
+
+	sk_mapped_addr6.sin6_port = S_PORT;
+	TEST_ERRNO(bind(sk6, (struct sockaddr *)&sk_mapped_addr6,
+			sizeof(sk_mapped_addr6)),
+		   EINVAL);
+
+	sk_addr6.sin6_port = S_PORT;
+	TEST_SUCC(bind(sk6, (struct sockaddr *)&sk_addr6, sizeof(sk_addr6)));
+	TEST_ERRNO(setsockopt(sk6, SOL_IPV6, IPV6_V6ONLY, &enable,
+			      sizeof(enable)),
+		   EINVAL);
+
+	TEST_SUCC(close(sk4));
+	TEST_SUCC(close(sk6));
+}
+END_TEST()
+
+FN_TEST(udp_loopback)
+{
+	char buf[1];
+	struct sockaddr_in6 saddr;
+	socklen_t addrlen = sizeof(saddr);
+	int sk1 = TEST_SUCC(socket(PF_INET6, SOCK_DGRAM, 0));
+	int sk2 = TEST_SUCC(socket(PF_INET6, SOCK_DGRAM, 0));
+
+	sk_addr6.sin6_port = S_PORT;
+	TEST_SUCC(bind(sk1, (struct sockaddr *)&sk_addr6, sizeof(sk_addr6)));
+
+	sk_addr6.sin6_port = C_PORT;
+	TEST_SUCC(bind(sk2, (struct sockaddr *)&sk_addr6, sizeof(sk_addr6)));
+
+	sk_addr6.sin6_port = S_PORT;
+	buf[0] = 'a';
+	TEST_RES(sendto(sk2, buf, 1, 0, (struct sockaddr *)&sk_addr6,
+			sizeof(sk_addr6)),
+		 _ret == 1);
+
+	buf[0] = 0;
+	TEST_RES(recvfrom(sk1, buf, 1, 0, (struct sockaddr *)&saddr,
+			  &addrlen),
+		 _ret == 1 && buf[0] == 'a' && addrlen == sizeof(saddr) &&
+			 saddr.sin6_family == AF_INET6 &&
+			 saddr.sin6_port == C_PORT &&
+			 IN6_IS_ADDR_LOOPBACK(&saddr.sin6_addr));
+
+	TEST_SUCC(close(sk1));
+	TEST_SUCC(close(sk2));
+}
+END_TEST()
+
+FN_TEST(udp_mapped)
+{
+	char buf[1];
+	struct sockaddr_in6 saddr;
+	socklen_t addrlen = sizeof(saddr);
+	int sk6 = TEST_SUCC(socket(PF_INET6, SOCK_DGRAM, 0));
+	int sk4 = TEST_SUCC(socket(PF_INET, SOCK_DGRAM, 0));
+
+	sk_mapped_addr6.sin6_port = S_PORT;
+	TEST_SUCC(bind(sk6, (struct sockaddr *)&sk_mapped_addr6,
+		       sizeof(sk_mapped_addr6)));
+
+	sk_addr4.sin_port = C_PORT;
+	TEST_SUCC(bind(sk4, (struct sockaddr *)&sk_addr4, sizeof(sk_addr4)));
+
+	sk_addr4.sin_port = S_PORT;
+	buf[0] = 'b';
+	TEST_RES(sendto(sk4, buf, 1, 0, (struct sockaddr *)&sk_addr4,
+			sizeof(sk_addr4)),
+		 _ret == 1);
+
+	buf[0] = 0;
+	TEST_RES(recvfrom(sk6, buf, 1, 0, (struct sockaddr *)&saddr,
+			  &addrlen),
+		 _ret == 1 && buf[0] == 'b' && addrlen == sizeof(saddr) &&
+			 saddr.sin6_family == AF_INET6 &&
+			 saddr.sin6_port == C_PORT &&
+			 memcmp(&saddr.sin6_addr, &sk_mapped_addr6.sin6_addr,
+				sizeof(saddr.sin6_addr)) == 0);
+
+	TEST_SUCC(close(sk6));
+	TEST_SUCC(close(sk4));
+}
+END_TEST()
+
+FN_TEST(tcp_loopback)
+{
+	char buf[1];
+	struct sockaddr_in6 saddr;
+	socklen_t addrlen = sizeof(saddr);
+	int sk_listen = TEST_SUCC(socket(PF_INET6, SOCK_STREAM, 0));
+	int sk_connect = TEST_SUCC(socket(PF_INET6, SOCK_STREAM, 0));
+	int sk_accepted;
+
+	sk_addr6.sin6_port = S_PORT;
+	TEST_SUCC(bind(sk_listen, (struct sockaddr *)&sk_addr6,
+		       sizeof(sk_addr6)));
+	TEST_SUCC(listen(sk_listen, 1));
+
+	TEST_SUCC(connect(sk_connect, (struct sockaddr *)&sk_addr6,
+			  sizeof(sk_addr6)));
+	sk_accepted = TEST_RES(accept(sk_listen, (struct sockaddr *)&saddr,
+				      &addrlen),
+			       addrlen == sizeof(saddr) &&
+				       saddr.sin6_family == AF_INET6 &&
+				       IN6_IS_ADDR_LOOPBACK(&saddr.sin6_addr));
+
+	buf[0] = 'c';
+	TEST_RES(send(sk_connect, buf, 1, 0), _ret == 1);
+	buf[0] = 0;
+	TEST_RES(recv(sk_accepted, buf, 1, 0), _ret == 1 && buf[0] == 'c');
+
+	TEST_SUCC(close(sk_accepted));
+	TEST_SUCC(close(sk_connect));
+	TEST_SUCC(close(sk_listen));
+}
+END_TEST()
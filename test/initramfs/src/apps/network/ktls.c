@@ -0,0 +1,188 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <unistd.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <netinet/in.h>
+#include <netinet/tcp.h>
+#include <arpa/inet.h>
+#include <linux/tls.h>
+
+#include "../common/test.h"
+
+#define TLS_RECORD_TYPE_HANDSHAKE 22
+#define TLS_RECORD_TYPE_DATA 23
+
+static int sk_unconnected;
+static int sk_listen;
+static int sk_connect;
+static int sk_accept;
+
+static struct tls12_crypto_info_aes_gcm_128 crypto_info;
+
+FN_SETUP(init)
+{
+	struct sockaddr_in addr = { .sin_family = AF_INET };
+	socklen_t addrlen = sizeof(addr);
+
+	CHECK(inet_aton("127.0.0.1", &addr.sin_addr));
+
+	sk_unconnected = CHECK(socket(AF_INET, SOCK_STREAM, 0));
+	sk_listen = CHECK(socket(AF_INET, SOCK_STREAM, 0));
+	sk_connect = CHECK(socket(AF_INET, SOCK_STREAM, 0));
+
+	CHECK(bind(sk_listen, (struct sockaddr *)&addr, addrlen));
+	CHECK(getsockname(sk_listen, (struct sockaddr *)&addr, &addrlen));
+	CHECK(listen(sk_listen, 1));
+	CHECK(connect(sk_connect, (struct sockaddr *)&addr, addrlen));
+	sk_accept = CHECK(accept(sk_listen, NULL, NULL));
+
+	crypto_info.info.version = TLS_1_2_VERSION;
+	crypto_info.info.cipher_type = TLS_CIPHER_AES_GCM_128;
+	memset(crypto_info.key, 0x11, sizeof(crypto_info.key));
+	memset(crypto_info.salt, 0x22, sizeof(crypto_info.salt));
+	memset(crypto_info.iv, 0x33, sizeof(crypto_info.iv));
+	memset(crypto_info.rec_seq, 0, sizeof(crypto_info.rec_seq));
+}
+END_SETUP()
+
+static int set_ulp(int sk, const char *name)
+{
+	return setsockopt(sk, IPPROTO_TCP, TCP_ULP, name, strlen(name));
+}
+
+static int set_crypto_info(int sk, int name)
+{
+	return setsockopt(sk, SOL_TLS, name, &crypto_info, sizeof(crypto_info));
+}
+
+FN_TEST(ulp)
+{
+	char name[16];
+	socklen_t len;
+
+	TEST_ERRNO(set_ulp(sk_unconnected, "tls"), ENOTCONN);
+	TEST_ERRNO(set_ulp(sk_connect, "none"), ENOENT);
+	TEST_ERRNO(set_crypto_info(sk_connect, TLS_TX), ENOPROTOOPT);
+
+	len = sizeof(name);
+	TEST_RES(getsockopt(sk_connect, IPPROTO_TCP, TCP_ULP, name, &len),
+		 len == 0);
+
+	TEST_SUCC(set_ulp(sk_connect, "tls"));
+	TEST_SUCC(set_ulp(sk_accept, "tls"));
+	TEST_ERRNO(set_ulp(sk_connect, "tls"), EEXIST);
+
+	len = sizeof(name);
+	TEST_RES(getsockopt(sk_connect, IPPROTO_TCP, TCP_ULP, name, &len),
+		 strcmp(name, "tls") == 0);
+}
+END_TEST()
+
+FN_TEST(crypto_info)
+{
+	struct tls12_crypto_info_aes_gcm_128 info;
+	socklen_t len = sizeof(info);
+
+	TEST_ERRNO(getsockopt(sk_connect, SOL_TLS, TLS_TX, &info, &len),
+		   EBUSY);
+
+	crypto_info.info.cipher_type = TLS_CIPHER_AES_CCM_128;
+	TEST_ERRNO(set_crypto_info(sk_connect, TLS_TX), EINVAL);
+	crypto_info.info.cipher_type = TLS_CIPHER_AES_GCM_128;
+
+	TEST_SUCC(set_crypto_info(sk_connect, TLS_TX));
+	TEST_SUCC(set_crypto_info(sk_accept, TLS_RX));
+	TEST_ERRNO(set_crypto_info(sk_connect, TLS_TX), EBUSY);
+
+	len = sizeof(info);
+	TEST_RES(getsockopt(sk_connect, SOL_TLS, TLS_TX, &info, &len),
+		 len == sizeof(info) &&
+			 memcmp(&info, &crypto_info, sizeof(info)) == 0);
+}
+END_TEST()
+
+static struct msghdr msg;
+
+static int send_record(const char *data, unsigned char record_type)
+{
+	char control[CMSG_SPACE(sizeof(record_type))];
+	struct iovec iov = { .iov_base = (void *)data,
+			     .iov_len = strlen(data) };
+	struct cmsghdr *cmsg;
+
+	memset(&msg, 0, sizeof(msg));
+	msg.msg_iov = &iov;
+	msg.msg_iovlen = 1;
+	msg.msg_control = control;
+	msg.msg_controllen = sizeof(control);
+
+	cmsg = CMSG_FIRSTHDR(&msg);
+	cmsg->cmsg_level = SOL_TLS;
+	cmsg->cmsg_type = TLS_SET_RECORD_TYPE;
+	cmsg->cmsg_len = CMSG_LEN(sizeof(record_type));
+	*CMSG_DATA(cmsg) = record_type;
+
+	return sendmsg(sk_connect, &msg, 0);
+}
+
+static char buf[64];
+
+static int recv_record(void)
+{
+	static char control[64];
+	static struct iovec iov = { .iov_base = buf, .iov_len = sizeof(buf) };
+
+	memset(buf, 0, sizeof(buf));
+	memset(&msg, 0, sizeof(msg));
+	msg.msg_iov = &iov;
+	msg.msg_iovlen = 1;
+	msg.msg_control = control;
+	msg.msg_controllen = sizeof(control);
+
+	return recvmsg(sk_accept, &msg, 0);
+}
+
+static int recv_type(void)
+{
+	struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+
+	if (cmsg == NULL || cmsg->cmsg_level != SOL_TLS ||
+	    cmsg->cmsg_type != TLS_GET_RECORD_TYPE)
+		return -1;
+
+	return *CMSG_DATA(cmsg);
+}
+
+FN_TEST(send_and_recv)
+{
+	TEST_RES(send(sk_connect, "hello", 5, 0), _ret == 5);
+	TEST_RES(recv_record(),
+		 _ret == 5 && strcmp(buf, "hello") == 0 &&
+			 recv_type() == TLS_RECORD_TYPE_DATA);
+
+	// Records of different types are never received together.
+	TEST_RES(send_record("abc", TLS_RECORD_TYPE_HANDSHAKE), _ret == 3);
+	TEST_RES(send(sk_connect, "def", 3, 0), _ret == 3);
+	TEST_RES(send(sk_connect, "ghi", 3, 0), _ret == 3);
+	TEST_RES(recv_record(),
+		 _ret == 3 && strcmp(buf, "abc") == 0 &&
+			 recv_type() == TLS_RECORD_TYPE_HANDSHAKE);
+	TEST_RES(recv(sk_accept, buf, 3, MSG_PEEK),
+		 _ret == 3 && memcmp(buf, "def", 3) == 0);
+	TEST_RES(recv_record(),
+		 _ret == 6 && strcmp(buf, "defghi") == 0 &&
+			 recv_type() == TLS_RECORD_TYPE_DATA);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(sk_unconnected));
+	CHECK(close(sk_listen));
+	CHECK(close(sk_connect));
+	CHECK(close(sk_accept));
+}
+END_SETUP()
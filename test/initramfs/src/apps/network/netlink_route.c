@@ -2,6 +2,9 @@
 
 #include <net/if.h>
 #include <netlink/route/addr.h>
+#include <netlink/route/link/bridge.h>
+#include <netlink/route/link/veth.h>
+#include <netlink/route/link/vlan.h>
 #include <unistd.h>
 
 #include "../common/test.h"
@@ -332,6 +335,128 @@ FN_TEST(get_addr_error)
 }
 END_TEST()
 
+int find_new_route_until_done(char *buffer, size_t len, int *found_new_route)
+{
+	struct nlmsghdr *nlh = (struct nlmsghdr *)buffer;
+
+	for (; NLMSG_OK(nlh, len); nlh = NLMSG_NEXT(nlh, len)) {
+		if (nlh->nlmsg_type == NLMSG_DONE) {
+			return *found_new_route ? 1 : -1;
+		}
+
+		if (nlh->nlmsg_type == RTM_NEWROUTE &&
+		    ((struct rtmsg *)NLMSG_DATA(nlh))->rtm_family == AF_INET) {
+			*found_new_route += 1;
+		} else {
+			return -1;
+		}
+	}
+
+	return 0;
+}
+
+FN_TEST(get_route)
+{
+	int sock_fd;
+	struct {
+		struct nlmsghdr hdr;
+		struct rtmsg rtm;
+	} req;
+
+	sock_fd = TEST_SUCC(socket(AF_NETLINK, SOCK_RAW, NETLINK_ROUTE));
+
+	memset(&req, 0, sizeof(req));
+	req.hdr.nlmsg_len = sizeof(req);
+	req.hdr.nlmsg_type = RTM_GETROUTE;
+	req.hdr.nlmsg_flags = NLM_F_REQUEST | NLM_F_DUMP;
+	req.hdr.nlmsg_seq = 1;
+	req.rtm.rtm_family = AF_INET;
+
+	TEST_RES(send(sock_fd, &req, sizeof(req), 0), _ret == sizeof(req));
+
+	int found_new_route = 0;
+	while (1) {
+		size_t recv_len =
+			TEST_SUCC(recv(sock_fd, buffer, BUFFER_SIZE, 0));
+
+		int found_done = TEST_RES(find_new_route_until_done(
+						  buffer, recv_len,
+						  &found_new_route),
+					  _ret >= 0);
+
+		if (found_done != 0) {
+			break;
+		}
+	}
+
+	TEST_SUCC(close(sock_fd));
+}
+END_TEST()
+
+struct nl_addr6_req {
+	struct nlmsghdr hdr;
+	struct ifaddrmsg ifa;
+	struct nlattr ahdr;
+	struct in6_addr addr;
+};
+
+FN_TEST(new_and_del_addr)
+{
+	int sock_fd;
+	struct nl_addr6_req req;
+
+	sock_fd = TEST_SUCC(socket(AF_NETLINK, SOCK_RAW, NETLINK_ROUTE));
+
+	memset(&req, 0, sizeof(req));
+	req.hdr.nlmsg_len = sizeof(req);
+	req.hdr.nlmsg_type = RTM_NEWADDR;
+	req.hdr.nlmsg_flags = NLM_F_REQUEST | NLM_F_ACK | NLM_F_CREATE |
+			      NLM_F_EXCL;
+	req.hdr.nlmsg_seq = 1;
+	req.ifa.ifa_family = AF_INET6;
+	req.ifa.ifa_prefixlen = 64;
+	req.ifa.ifa_index = if_nametoindex(LOOPBACK_NAME);
+	req.ahdr.nla_type = IFA_LOCAL;
+	req.ahdr.nla_len = sizeof(req.ahdr) + sizeof(req.addr);
+	// 2001:db8::1
+	req.addr.s6_addr[0] = 0x20;
+	req.addr.s6_addr[1] = 0x01;
+	req.addr.s6_addr[2] = 0x0d;
+	req.addr.s6_addr[3] = 0xb8;
+	req.addr.s6_addr[15] = 0x01;
+
+#define TEST_ACK_SEGMENT(errno)                                               \
+	TEST_RES(send(sock_fd, &req, sizeof(req), 0), _ret == sizeof(req));   \
+	TEST_RES(recv(sock_fd, buffer, BUFFER_SIZE, 0),                       \
+		 ((struct nlmsghdr *)buffer)->nlmsg_type == NLMSG_ERROR &&    \
+			 ((struct nlmsgerr *)NLMSG_DATA(buffer))->error ==    \
+				 -errno);
+
+	// Add the address
+	TEST_ACK_SEGMENT(0);
+
+	// Add the address again
+	TEST_ACK_SEGMENT(EEXIST);
+
+	// Add the address to a non-existent interface
+	req.ifa.ifa_index = 9999;
+	TEST_ACK_SEGMENT(ENODEV);
+
+	// Delete the address
+	req.hdr.nlmsg_type = RTM_DELADDR;
+	req.hdr.nlmsg_flags = NLM_F_REQUEST | NLM_F_ACK;
+	req.ifa.ifa_index = if_nametoindex(LOOPBACK_NAME);
+	TEST_ACK_SEGMENT(0);
+
+	// Delete the address again
+	TEST_ACK_SEGMENT(EADDRNOTAVAIL);
+
+#undef TEST_ACK_SEGMENT
+
+	TEST_SUCC(close(sock_fd));
+}
+END_TEST()
+
 FN_TEST(bufsize_msgsize)
 {
 	int sock_fd;
@@ -397,3 +522,125 @@ FN_TEST(enobufs)
 	TEST_SUCC(close(sock_fd));
 }
 END_TEST()
+
+FN_TEST(veth_and_bridge)
+{
+	struct nl_sock *sock;
+	struct rtnl_link *link;
+	int veth0, veth1, br0;
+
+	sock = nl_socket_alloc();
+	TEST_RES(nl_connect(sock, NETLINK_ROUTE), _ret >= 0);
+
+	// Create a veth pair and a bridge
+	TEST_RES(rtnl_link_veth_add(sock, "veth0", "veth1", getpid()),
+		 _ret == 0);
+	TEST_RES(rtnl_link_bridge_add(sock, "br0"), _ret == 0);
+	TEST_RES(rtnl_link_bridge_add(sock, "br0"), _ret < 0);
+
+	veth0 = TEST_RES(if_nametoindex("veth0"), _ret != 0);
+	veth1 = TEST_RES(if_nametoindex("veth1"), _ret != 0);
+	br0 = TEST_RES(if_nametoindex("br0"), _ret != 0);
+
+	TEST_RES(rtnl_link_get_kernel(sock, veth0, NULL, &link),
+		 _ret == 0 && rtnl_link_get_link(link) == veth1 &&
+			 strcmp(rtnl_link_get_type(link), "veth") == 0);
+	rtnl_link_put(link);
+	TEST_RES(rtnl_link_get_kernel(sock, br0, NULL, &link),
+		 _ret == 0 && strcmp(rtnl_link_get_type(link), "bridge") == 0);
+	rtnl_link_put(link);
+
+	// Enslave a link to the bridge and release it
+	TEST_RES(rtnl_link_enslave_ifindex(sock, br0, veth1), _ret == 0);
+	TEST_RES(rtnl_link_get_kernel(sock, veth1, NULL, &link),
+		 _ret == 0 && rtnl_link_get_master(link) == br0);
+	rtnl_link_put(link);
+	TEST_RES(rtnl_link_release_ifindex(sock, veth1), _ret == 0);
+
+	// Deleting one end of the veth pair deletes both ends
+	link = rtnl_link_alloc();
+	rtnl_link_set_ifindex(link, veth0);
+	TEST_RES(rtnl_link_delete(sock, link), _ret == 0);
+	TEST_ERRNO(if_nametoindex("veth1"), ENODEV);
+	rtnl_link_set_ifindex(link, br0);
+	TEST_RES(rtnl_link_delete(sock, link), _ret == 0);
+	TEST_ERRNO(if_nametoindex("br0"), ENODEV);
+	rtnl_link_put(link);
+
+	// Links that are not virtual links cannot be deleted
+	link = rtnl_link_alloc();
+	rtnl_link_set_ifindex(link, if_nametoindex(LOOPBACK_NAME));
+	TEST_RES(rtnl_link_delete(sock, link), _ret < 0);
+	rtnl_link_put(link);
+
+	nl_close(sock);
+	nl_socket_free(sock);
+}
+END_TEST()
+
+FN_TEST(vlan_and_mtu)
+{
+	struct nl_sock *sock;
+	struct rtnl_link *link, *change;
+	int veth0, vlan;
+
+	sock = nl_socket_alloc();
+	TEST_RES(nl_connect(sock, NETLINK_ROUTE), _ret >= 0);
+
+	TEST_RES(rtnl_link_veth_add(sock, "veth0", "veth1", getpid()),
+		 _ret == 0);
+	veth0 = TEST_RES(if_nametoindex("veth0"), _ret != 0);
+
+	// Create a VLAN link on top of a veth link
+	link = rtnl_link_vlan_alloc();
+	rtnl_link_set_name(link, "veth0.10");
+	rtnl_link_set_link(link, veth0);
+	rtnl_link_vlan_set_id(link, 10);
+	TEST_RES(rtnl_link_add(sock, link, NLM_F_CREATE), _ret == 0);
+	// The VLAN ID is already in use
+	rtnl_link_set_name(link, "veth0.10b");
+	TEST_RES(rtnl_link_add(sock, link, NLM_F_CREATE), _ret < 0);
+	rtnl_link_put(link);
+
+	vlan = TEST_RES(if_nametoindex("veth0.10"), _ret != 0);
+	TEST_RES(rtnl_link_get_kernel(sock, vlan, NULL, &link),
+		 _ret == 0 && rtnl_link_is_vlan(link) &&
+			 rtnl_link_vlan_get_id(link) == 10 &&
+			 rtnl_link_get_link(link) == veth0 &&
+			 rtnl_link_get_mtu(link) == 1500);
+	rtnl_link_put(link);
+
+	// Reducing the MTU of the parent link reduces the MTU of the VLAN link
+	TEST_RES(rtnl_link_get_kernel(sock, veth0, NULL, &link), _ret == 0);
+	change = rtnl_link_alloc();
+	rtnl_link_set_mtu(change, 1400);
+	TEST_RES(rtnl_link_change(sock, link, change, 0), _ret == 0);
+	rtnl_link_put(change);
+	rtnl_link_put(link);
+
+	TEST_RES(rtnl_link_get_kernel(sock, vlan, NULL, &link),
+		 _ret == 0 && rtnl_link_get_mtu(link) == 1400);
+	// The MTU of the VLAN link cannot exceed the MTU of the parent link
+	change = rtnl_link_alloc();
+	rtnl_link_set_mtu(change, 1500);
+	TEST_RES(rtnl_link_change(sock, link, change, 0), _ret < 0);
+	rtnl_link_set_mtu(change, 1300);
+	TEST_RES(rtnl_link_change(sock, link, change, 0), _ret == 0);
+	rtnl_link_put(change);
+	rtnl_link_put(link);
+
+	TEST_RES(rtnl_link_get_kernel(sock, vlan, NULL, &link),
+		 _ret == 0 && rtnl_link_get_mtu(link) == 1300);
+	rtnl_link_put(link);
+
+	// Deleting the parent link deletes the VLAN link
+	link = rtnl_link_alloc();
+	rtnl_link_set_ifindex(link, veth0);
+	TEST_RES(rtnl_link_delete(sock, link), _ret == 0);
+	TEST_ERRNO(if_nametoindex("veth0.10"), ENODEV);
+	rtnl_link_put(link);
+
+	nl_close(sock);
+	nl_socket_free(sock);
+}
+END_TEST()
@@ -0,0 +1,348 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+#include <poll.h>
+#include <string.h>
+#include <unistd.h>
+#include <sys/mman.h>
+#include <sys/socket.h>
+#include <arpa/inet.h>
+#include <net/ethernet.h>
+#include <net/if_arp.h>
+#include <netinet/in.h>
+#include <netinet/ip.h>
+#include <linux/filter.h>
+#include <linux/if_packet.h>
+
+#include "../common/test.h"
+
+// The loopback iface is always the first iface.
+#define LO_IFINDEX 1
+
+// An experimental protocol number, which is not used by other tests.
+#define TEST_PROTO 253
+
+#define PAYLOAD "PACKETS!"
+#define PAYLOAD_LEN (sizeof(PAYLOAD) - 1)
+
+struct test_frame {
+	struct ether_header eth;
+	struct iphdr ip;
+	char payload[PAYLOAD_LEN];
+} __attribute__((packed));
+
+static struct sockaddr_ll lo_addr = {
+	.sll_family = AF_PACKET,
+	.sll_protocol = __constant_htons(ETH_P_IP),
+	.sll_ifindex = LO_IFINDEX,
+};
+
+// Accepts only IPv4 packets of `TEST_PROTO`, so that other traffic on the
+// loopback iface is ignored.
+static struct sock_filter filter_code[] = {
+	BPF_STMT(BPF_LD | BPF_B | BPF_ABS, SKF_NET_OFF + 9),
+	BPF_JUMP(BPF_JMP | BPF_JEQ | BPF_K, TEST_PROTO, 0, 1),
+	BPF_STMT(BPF_RET | BPF_K, 0xffff),
+	BPF_STMT(BPF_RET | BPF_K, 0),
+};
+
+static struct sock_fprog filter = {
+	.len = sizeof(filter_code) / sizeof(filter_code[0]),
+	.filter = filter_code,
+};
+
+static int sk_raw;
+static int sk_dgram;
+
+static unsigned short checksum(const void *data, size_t len)
+{
+	const unsigned short *words = data;
+	unsigned int sum = 0;
+
+	for (; len > 1; len -= 2)
+		sum += *words++;
+
+	sum = (sum >> 16) + (sum & 0xffff);
+	sum += sum >> 16;
+	return ~sum;
+}
+
+static void fill_frame(struct test_frame *frame)
+{
+	memset(frame, 0, sizeof(*frame));
+
+	frame->eth.ether_type = htons(ETH_P_IP);
+
+	frame->ip.version = 4;
+	frame->ip.ihl = 5;
+	frame->ip.tot_len = htons(sizeof(frame->ip) + PAYLOAD_LEN);
+	frame->ip.ttl = 64;
+	frame->ip.protocol = TEST_PROTO;
+	frame->ip.saddr = htonl(INADDR_LOOPBACK);
+	frame->ip.daddr = htonl(INADDR_LOOPBACK);
+	frame->ip.check = checksum(&frame->ip, sizeof(frame->ip));
+
+	memcpy(frame->payload, PAYLOAD, PAYLOAD_LEN);
+}
+
+static int new_bound_socket(int type)
+{
+	int sk;
+
+	sk = CHECK(socket(AF_PACKET, type, htons(ETH_P_IP)));
+	CHECK(bind(sk, (struct sockaddr *)&lo_addr, sizeof(lo_addr)));
+	CHECK(setsockopt(sk, SOL_SOCKET, SO_ATTACH_FILTER, &filter,
+			 sizeof(filter)));
+
+	return sk;
+}
+
+FN_SETUP(general)
+{
+	sk_raw = new_bound_socket(SOCK_RAW);
+	sk_dgram = new_bound_socket(SOCK_DGRAM);
+}
+END_SETUP()
+
+FN_TEST(bind)
+{
+	struct sockaddr_ll addr = lo_addr;
+	int sk;
+
+	sk = TEST_SUCC(socket(AF_PACKET, SOCK_DGRAM, 0));
+
+	TEST_ERRNO(bind(sk, (struct sockaddr *)&addr, sizeof(addr) - 1),
+		   EINVAL);
+
+	addr.sll_ifindex = 1000;
+	TEST_ERRNO(bind(sk, (struct sockaddr *)&addr, sizeof(addr)), ENODEV);
+
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(getsockname)
+{
+	struct sockaddr_ll addr;
+	socklen_t addrlen = sizeof(addr);
+
+	TEST_RES(getsockname(sk_raw, (struct sockaddr *)&addr, &addrlen),
+		 addrlen == sizeof(addr) && addr.sll_family == AF_PACKET &&
+			 addr.sll_protocol == htons(ETH_P_IP) &&
+			 addr.sll_ifindex == LO_IFINDEX &&
+			 addr.sll_hatype == ARPHRD_LOOPBACK &&
+			 addr.sll_halen == ETH_ALEN);
+}
+END_TEST()
+
+FN_TEST(send_without_iface)
+{
+	struct test_frame frame;
+	int sk;
+
+	fill_frame(&frame);
+
+	sk = TEST_SUCC(socket(AF_PACKET, SOCK_RAW, htons(ETH_P_IP)));
+	TEST_ERRNO(send(sk, &frame, sizeof(frame), 0), ENXIO);
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(send_raw)
+{
+	struct test_frame frame;
+	struct test_frame recv_frame;
+	struct iphdr recv_ip;
+	struct sockaddr_ll addr;
+	socklen_t addrlen = sizeof(addr);
+
+	fill_frame(&frame);
+	TEST_RES(send(sk_raw, &frame, sizeof(frame), 0),
+		 _ret == sizeof(frame));
+
+	// The sender only sees the frame received on the loopback iface.
+	TEST_RES(recvfrom(sk_raw, &recv_frame, sizeof(recv_frame),
+			  MSG_DONTWAIT, (struct sockaddr *)&addr, &addrlen),
+		 _ret == sizeof(frame) &&
+			 memcmp(&recv_frame, &frame, sizeof(frame)) == 0 &&
+			 addr.sll_pkttype == PACKET_HOST &&
+			 addr.sll_ifindex == LO_IFINDEX);
+	TEST_ERRNO(recv(sk_raw, &recv_frame, sizeof(recv_frame), MSG_DONTWAIT),
+		   EAGAIN);
+
+	// Other sockets also see the frame sent on the loopback iface.
+	TEST_RES(recvfrom(sk_dgram, &recv_ip, sizeof(recv_ip), MSG_DONTWAIT,
+			  (struct sockaddr *)&addr, &addrlen),
+		 _ret == sizeof(recv_ip) &&
+			 recv_ip.protocol == TEST_PROTO &&
+			 addr.sll_pkttype == PACKET_OUTGOING &&
+			 addr.sll_protocol == htons(ETH_P_IP));
+	TEST_RES(recv(sk_dgram, &recv_ip, sizeof(recv_ip),
+		      MSG_DONTWAIT | MSG_TRUNC),
+		 _ret == sizeof(frame.ip) + PAYLOAD_LEN &&
+			 recv_ip.protocol == TEST_PROTO);
+	TEST_ERRNO(recv(sk_dgram, &recv_ip, sizeof(recv_ip), MSG_DONTWAIT),
+		   EAGAIN);
+}
+END_TEST()
+
+FN_TEST(send_dgram)
+{
+	struct test_frame frame;
+	struct test_frame recv_frame;
+	struct sockaddr_ll addr;
+	socklen_t addrlen = sizeof(addr);
+
+	fill_frame(&frame);
+	TEST_RES(sendto(sk_dgram, &frame.ip, sizeof(frame) - sizeof(frame.eth),
+			0, (struct sockaddr *)&lo_addr, sizeof(lo_addr)),
+		 _ret == sizeof(frame) - sizeof(frame.eth));
+
+	// The Ethernet header is generated by the kernel.
+	TEST_RES(recvfrom(sk_raw, &recv_frame, sizeof(recv_frame),
+			  MSG_DONTWAIT | MSG_PEEK, (struct sockaddr *)&addr,
+			  &addrlen),
+		 _ret == sizeof(frame) &&
+			 memcmp(&recv_frame, &frame, sizeof(frame)) == 0 &&
+			 addr.sll_pkttype == PACKET_OUTGOING);
+	TEST_RES(recv(sk_raw, &recv_frame, sizeof(recv_frame), MSG_DONTWAIT),
+		 _ret == sizeof(frame));
+	TEST_RES(recvfrom(sk_raw, &recv_frame, sizeof(recv_frame),
+			  MSG_DONTWAIT, (struct sockaddr *)&addr, &addrlen),
+		 _ret == sizeof(frame) && addr.sll_pkttype == PACKET_HOST);
+	TEST_ERRNO(recv(sk_raw, &recv_frame, sizeof(recv_frame), MSG_DONTWAIT),
+		   EAGAIN);
+
+	TEST_RES(recv(sk_dgram, &recv_frame, sizeof(recv_frame), MSG_DONTWAIT),
+		 _ret == sizeof(frame) - sizeof(frame.eth));
+	TEST_ERRNO(recv(sk_dgram, &recv_frame, sizeof(recv_frame),
+			MSG_DONTWAIT),
+		   EAGAIN);
+}
+END_TEST()
+
+FN_TEST(statistics)
+{
+	struct tpacket_stats stats;
+	socklen_t len = sizeof(stats);
+
+	TEST_RES(getsockopt(sk_raw, SOL_PACKET, PACKET_STATISTICS, &stats,
+			    &len),
+		 len == sizeof(stats) && stats.tp_packets == 3 &&
+			 stats.tp_drops == 0);
+
+	// The statistics are reset after they are read.
+	TEST_RES(getsockopt(sk_raw, SOL_PACKET, PACKET_STATISTICS, &stats,
+			    &len),
+		 len == sizeof(stats) && stats.tp_packets == 0 &&
+			 stats.tp_drops == 0);
+}
+END_TEST()
+
+FN_TEST(detach_filter)
+{
+	int val = 0;
+	int sk;
+
+	sk = TEST_SUCC(socket(AF_PACKET, SOCK_RAW, 0));
+	TEST_ERRNO(setsockopt(sk, SOL_SOCKET, SO_DETACH_FILTER, &val,
+			      sizeof(val)),
+		   ENOENT);
+	TEST_SUCC(setsockopt(sk, SOL_SOCKET, SO_ATTACH_FILTER, &filter,
+			     sizeof(filter)));
+	TEST_SUCC(setsockopt(sk, SOL_SOCKET, SO_DETACH_FILTER, &val,
+			     sizeof(val)));
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(hdrlen)
+{
+	int val = TPACKET_V3;
+	socklen_t len = sizeof(val);
+
+	TEST_RES(getsockopt(sk_raw, SOL_PACKET, PACKET_HDRLEN, &val, &len),
+		 len == sizeof(val) && val == sizeof(struct tpacket3_hdr));
+
+	val = 3;
+	TEST_ERRNO(getsockopt(sk_raw, SOL_PACKET, PACKET_HDRLEN, &val, &len),
+		   EINVAL);
+}
+END_TEST()
+
+FN_TEST(rx_ring)
+{
+	struct tpacket_req3 req = {
+		.tp_block_size = 4096,
+		.tp_block_nr = 2,
+		.tp_frame_size = 2048,
+		.tp_frame_nr = 4,
+		.tp_retire_blk_tov = 10,
+	};
+	struct test_frame frame;
+	struct tpacket_block_desc *desc;
+	struct tpacket3_hdr *hdr;
+	struct pollfd pfd;
+	int version = TPACKET_V3;
+	char *ring;
+	int sk;
+
+	sk = new_bound_socket(SOCK_RAW);
+
+	TEST_ERRNO(setsockopt(sk, SOL_PACKET, PACKET_RX_RING, &req,
+			      sizeof(req)),
+		   EINVAL);
+	TEST_SUCC(setsockopt(sk, SOL_PACKET, PACKET_VERSION, &version,
+			     sizeof(version)));
+	TEST_SUCC(setsockopt(sk, SOL_PACKET, PACKET_RX_RING, &req,
+			     sizeof(req)));
+
+	TEST_ERRNO(setsockopt(sk, SOL_PACKET, PACKET_RX_RING, &req,
+			      sizeof(req)),
+		   EBUSY);
+	TEST_ERRNO(setsockopt(sk, SOL_PACKET, PACKET_VERSION, &version,
+			      sizeof(version)),
+		   EBUSY);
+
+	ring = TEST_SUCC(mmap(NULL, 2 * 4096, PROT_READ | PROT_WRITE,
+			      MAP_SHARED, sk, 0));
+	desc = (struct tpacket_block_desc *)ring;
+
+	fill_frame(&frame);
+	TEST_RES(send(sk_raw, &frame, sizeof(frame), 0),
+		 _ret == sizeof(frame));
+
+	// The block is retired after the timeout.
+	pfd.fd = sk;
+	pfd.events = POLLIN;
+	TEST_RES(poll(&pfd, 1, 1000), _ret == 1 && pfd.revents == POLLIN);
+
+	hdr = (struct tpacket3_hdr *)(ring +
+				      desc->hdr.bh1.offset_to_first_pkt);
+	TEST_RES(0, desc->version == TPACKET_V3 &&
+			    (desc->hdr.bh1.block_status & TP_STATUS_USER) &&
+			    desc->hdr.bh1.num_pkts == 1 &&
+			    hdr->tp_len == sizeof(frame) &&
+			    hdr->tp_snaplen == sizeof(frame) &&
+			    memcmp((char *)hdr + hdr->tp_mac, &frame,
+				   sizeof(frame)) == 0);
+
+	// Release the block to the kernel.
+	desc->hdr.bh1.block_status = TP_STATUS_KERNEL;
+
+	TEST_SUCC(munmap(ring, 2 * 4096));
+	TEST_SUCC(close(sk));
+
+	// Drain the frames received by the other sockets.
+	TEST_SUCC(recv(sk_raw, &frame, sizeof(frame), MSG_DONTWAIT));
+	TEST_SUCC(recv(sk_dgram, &frame, sizeof(frame), MSG_DONTWAIT));
+	TEST_SUCC(recv(sk_dgram, &frame, sizeof(frame), MSG_DONTWAIT));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(sk_raw));
+	CHECK(close(sk_dgram));
+}
+END_SETUP()
@@ -0,0 +1,170 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+#include <fcntl.h>
+#include <string.h>
+#include <unistd.h>
+#include <sys/socket.h>
+#include <netinet/in.h>
+#include <netinet/ip_icmp.h>
+#include <arpa/inet.h>
+
+#include "../common/test.h"
+
+#define PING_GROUP_RANGE "/proc/sys/net/ipv4/ping_group_range"
+
+static struct sockaddr_in lo_addr = {
+	.sin_family = AF_INET,
+	.sin_addr = { .s_addr = htonl(INADDR_LOOPBACK) },
+};
+
+static int write_range(const char *range)
+{
+	int fd, ret;
+
+	fd = open(PING_GROUP_RANGE, O_WRONLY);
+	if (fd < 0)
+		return fd;
+
+	ret = write(fd, range, strlen(range));
+	close(fd);
+	return ret;
+}
+
+static int read_range(char *buf, size_t len)
+{
+	int fd, ret;
+
+	fd = open(PING_GROUP_RANGE, O_RDONLY);
+	if (fd < 0)
+		return fd;
+
+	ret = read(fd, buf, len - 1);
+	close(fd);
+	if (ret >= 0)
+		buf[ret] = '\0';
+	return ret;
+}
+
+FN_TEST(ping_group_range)
+{
+	char buf[64];
+
+	TEST_RES(read_range(buf, sizeof(buf)), strcmp(buf, "1\t0\n") == 0);
+	TEST_ERRNO(socket(AF_INET, SOCK_DGRAM, IPPROTO_ICMP), EACCES);
+
+	TEST_ERRNO(write_range("0 4294967295"), EINVAL);
+	TEST_RES(read_range(buf, sizeof(buf)), strcmp(buf, "1\t0\n") == 0);
+
+	// The end of the range is unchanged if only the start is written.
+	TEST_SUCC(write_range("0"));
+	TEST_RES(read_range(buf, sizeof(buf)), strcmp(buf, "0\t0\n") == 0);
+
+	// An inverted range is turned into the default empty range.
+	TEST_SUCC(write_range("5 3"));
+	TEST_RES(read_range(buf, sizeof(buf)), strcmp(buf, "1\t0\n") == 0);
+
+	TEST_SUCC(write_range("0 2147483647\n"));
+	TEST_RES(read_range(buf, sizeof(buf)),
+		 strcmp(buf, "0\t2147483647\n") == 0);
+}
+END_TEST()
+
+static int sk_ping;
+
+FN_SETUP(ping_socket)
+{
+	sk_ping = CHECK(socket(AF_INET, SOCK_DGRAM, IPPROTO_ICMP));
+}
+END_SETUP()
+
+FN_TEST(echo)
+{
+	struct icmphdr req = { .type = ICMP_ECHO };
+	struct {
+		struct icmphdr hdr;
+		char data[8];
+	} reply;
+	struct sockaddr_in addr, src_addr;
+	socklen_t addrlen = sizeof(addr);
+	socklen_t src_addrlen = sizeof(src_addr);
+
+	req.un.echo.sequence = htons(1);
+
+	TEST_RES(sendto(sk_ping, &req, sizeof(req), 0,
+			(struct sockaddr *)&lo_addr, sizeof(lo_addr)),
+		 _ret == sizeof(req));
+
+	// The identifier is picked when the socket is bound implicitly.
+	TEST_RES(getsockname(sk_ping, (struct sockaddr *)&addr, &addrlen),
+		 addrlen == sizeof(addr) && addr.sin_port != 0);
+
+	// The reply contains the ICMP message without the IP header.
+	TEST_RES(recvfrom(sk_ping, &reply, sizeof(reply), MSG_DONTWAIT,
+			  (struct sockaddr *)&src_addr, &src_addrlen),
+		 _ret == sizeof(req) && reply.hdr.type == ICMP_ECHOREPLY &&
+			 reply.hdr.un.echo.id == addr.sin_port &&
+			 reply.hdr.un.echo.sequence == htons(1) &&
+			 src_addr.sin_addr.s_addr == htonl(INADDR_LOOPBACK) &&
+			 src_addr.sin_port == 0);
+
+	TEST_ERRNO(recv(sk_ping, &reply, sizeof(reply), MSG_DONTWAIT), EAGAIN);
+}
+END_TEST()
+
+FN_TEST(invalid_request)
+{
+	struct icmphdr req = { .type = ICMP_ECHOREPLY };
+
+	TEST_ERRNO(sendto(sk_ping, &req, sizeof(req), 0,
+			  (struct sockaddr *)&lo_addr, sizeof(lo_addr)),
+		   EINVAL);
+
+	req.type = ICMP_ECHO;
+	TEST_ERRNO(sendto(sk_ping, &req, sizeof(req) - 1, 0,
+			  (struct sockaddr *)&lo_addr, sizeof(lo_addr)),
+		   EINVAL);
+}
+END_TEST()
+
+FN_TEST(options)
+{
+	int hdrincl = 1;
+	unsigned int filter = 0;
+
+	TEST_ERRNO(setsockopt(sk_ping, SOL_IP, IP_HDRINCL, &hdrincl,
+			      sizeof(hdrincl)),
+		   ENOPROTOOPT);
+	TEST_ERRNO(setsockopt(sk_ping, SOL_RAW, ICMP_FILTER, &filter,
+			      sizeof(filter)),
+		   ENOPROTOOPT);
+}
+END_TEST()
+
+FN_TEST(bind_ident)
+{
+	struct sockaddr_in addr = lo_addr;
+	socklen_t addrlen = sizeof(addr);
+	int sk;
+
+	sk = TEST_SUCC(socket(AF_INET, SOCK_DGRAM, IPPROTO_ICMP));
+
+	// The identifier is specified by the port.
+	addr.sin_port = htons(4242);
+	TEST_SUCC(bind(sk, (struct sockaddr *)&addr, sizeof(addr)));
+	TEST_RES(getsockname(sk, (struct sockaddr *)&addr, &addrlen),
+		 addrlen == sizeof(addr) && addr.sin_port == htons(4242));
+
+	TEST_ERRNO(bind(sk_ping, (struct sockaddr *)&addr, sizeof(addr)),
+		   EINVAL);
+
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(sk_ping));
+	CHECK(write_range("1 0"));
+}
+END_SETUP()
@@ -0,0 +1,205 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+#include <unistd.h>
+#include <sys/socket.h>
+#include <netinet/in.h>
+#include <netinet/ip.h>
+#include <netinet/ip_icmp.h>
+#include <arpa/inet.h>
+
+#include "../common/test.h"
+
+static struct sockaddr_in lo_addr = {
+	.sin_family = AF_INET,
+	.sin_addr = { .s_addr = htonl(INADDR_LOOPBACK) },
+};
+
+static int sk_icmp;
+static int sk_raw;
+
+struct ip_icmp_packet {
+	struct iphdr ip;
+	struct icmphdr icmp;
+};
+
+static unsigned short checksum(const void *data, size_t len)
+{
+	const unsigned short *words = data;
+	unsigned int sum = 0;
+
+	for (; len > 1; len -= 2)
+		sum += *words++;
+	if (len == 1)
+		sum += *(const unsigned char *)words;
+
+	sum = (sum >> 16) + (sum & 0xffff);
+	sum += sum >> 16;
+	return ~sum;
+}
+
+static void fill_echo_request(struct icmphdr *icmp, unsigned short seq)
+{
+	icmp->type = ICMP_ECHO;
+	icmp->code = 0;
+	icmp->un.echo.id = htons(0x1234);
+	icmp->un.echo.sequence = htons(seq);
+	icmp->checksum = 0;
+	icmp->checksum = checksum(icmp, sizeof(*icmp));
+}
+
+FN_SETUP(general)
+{
+	sk_icmp = CHECK(socket(AF_INET, SOCK_RAW, IPPROTO_ICMP));
+	sk_raw = CHECK(socket(AF_INET, SOCK_RAW, IPPROTO_RAW));
+}
+END_SETUP()
+
+FN_TEST(invalid_protocol)
+{
+	TEST_ERRNO(socket(AF_INET, SOCK_RAW, 0), EPROTONOSUPPORT);
+}
+END_TEST()
+
+FN_TEST(getsockname)
+{
+	struct sockaddr_in addr;
+	socklen_t addrlen = sizeof(addr);
+
+	// The port is the protocol number.
+	TEST_RES(getsockname(sk_icmp, (struct sockaddr *)&addr, &addrlen),
+		 addrlen == sizeof(addr) && addr.sin_addr.s_addr == 0 &&
+			 addr.sin_port == htons(IPPROTO_ICMP));
+}
+END_TEST()
+
+FN_TEST(hdrincl)
+{
+	int hdrincl;
+	socklen_t len = sizeof(hdrincl);
+
+	TEST_RES(getsockopt(sk_icmp, SOL_IP, IP_HDRINCL, &hdrincl, &len),
+		 hdrincl == 0 && len == sizeof(hdrincl));
+	TEST_RES(getsockopt(sk_raw, SOL_IP, IP_HDRINCL, &hdrincl, &len),
+		 hdrincl == 1 && len == sizeof(hdrincl));
+}
+END_TEST()
+
+FN_TEST(icmp_filter)
+{
+	unsigned int filter;
+	socklen_t len = sizeof(filter);
+	int sk_udp;
+
+	TEST_RES(getsockopt(sk_icmp, SOL_RAW, ICMP_FILTER, &filter, &len),
+		 filter == 0 && len == sizeof(filter));
+
+	TEST_ERRNO(getsockopt(sk_raw, SOL_RAW, ICMP_FILTER, &filter, &len),
+		   EOPNOTSUPP);
+
+	sk_udp = TEST_SUCC(socket(AF_INET, SOCK_DGRAM, 0));
+	TEST_ERRNO(getsockopt(sk_udp, SOL_RAW, ICMP_FILTER, &filter, &len),
+		   ENOPROTOOPT);
+	TEST_SUCC(close(sk_udp));
+}
+END_TEST()
+
+FN_TEST(echo)
+{
+	struct icmphdr req;
+	struct ip_icmp_packet pkt;
+	struct sockaddr_in addr;
+	socklen_t addrlen = sizeof(addr);
+	int ttl = 42;
+
+	fill_echo_request(&req, 1);
+	TEST_SUCC(setsockopt(sk_icmp, SOL_IP, IP_TTL, &ttl, sizeof(ttl)));
+	TEST_RES(sendto(sk_icmp, &req, sizeof(req), 0,
+			(struct sockaddr *)&lo_addr, sizeof(lo_addr)),
+		 _ret == sizeof(req));
+
+	// Raw sockets receive the IP headers. The request is looped back before
+	// the reply is generated.
+	TEST_RES(recvfrom(sk_icmp, &pkt, sizeof(pkt), MSG_DONTWAIT,
+			  (struct sockaddr *)&addr, &addrlen),
+		 _ret == sizeof(pkt) && pkt.ip.protocol == IPPROTO_ICMP &&
+			 pkt.ip.ttl == ttl && pkt.icmp.type == ICMP_ECHO &&
+			 addr.sin_addr.s_addr == htonl(INADDR_LOOPBACK) &&
+			 addr.sin_port == 0);
+	TEST_RES(recv(sk_icmp, &pkt, sizeof(pkt), MSG_DONTWAIT),
+		 _ret == sizeof(pkt) && pkt.icmp.type == ICMP_ECHOREPLY &&
+			 pkt.icmp.un.echo.id == htons(0x1234) &&
+			 pkt.icmp.un.echo.sequence == htons(1));
+	TEST_ERRNO(recv(sk_icmp, &pkt, sizeof(pkt), MSG_DONTWAIT), EAGAIN);
+}
+END_TEST()
+
+FN_TEST(echo_filtered)
+{
+	struct icmphdr req;
+	struct ip_icmp_packet pkt;
+	unsigned int filter = 1 << ICMP_ECHO;
+
+	TEST_SUCC(setsockopt(sk_icmp, SOL_RAW, ICMP_FILTER, &filter,
+			     sizeof(filter)));
+
+	fill_echo_request(&req, 2);
+	TEST_RES(sendto(sk_icmp, &req, sizeof(req), 0,
+			(struct sockaddr *)&lo_addr, sizeof(lo_addr)),
+		 _ret == sizeof(req));
+
+	// Only the reply is received.
+	TEST_RES(recv(sk_icmp, &pkt, sizeof(pkt), MSG_DONTWAIT),
+		 _ret == sizeof(pkt) && pkt.icmp.type == ICMP_ECHOREPLY &&
+			 pkt.icmp.un.echo.sequence == htons(2));
+	TEST_ERRNO(recv(sk_icmp, &pkt, sizeof(pkt), MSG_DONTWAIT), EAGAIN);
+
+	filter = 0;
+	TEST_SUCC(setsockopt(sk_icmp, SOL_RAW, ICMP_FILTER, &filter,
+			     sizeof(filter)));
+}
+END_TEST()
+
+FN_TEST(echo_hdrincl)
+{
+	struct ip_icmp_packet req = {
+		.ip = {
+			.version = 4,
+			.ihl = 5,
+			.ttl = 7,
+			.protocol = IPPROTO_ICMP,
+			.saddr = htonl(INADDR_LOOPBACK),
+			.daddr = htonl(INADDR_LOOPBACK),
+		},
+	};
+	struct ip_icmp_packet pkt;
+
+	fill_echo_request(&req.icmp, 3);
+
+	// The total length and the checksum are filled in.
+	TEST_RES(sendto(sk_raw, &req, sizeof(req), 0,
+			(struct sockaddr *)&lo_addr, sizeof(lo_addr)),
+		 _ret == sizeof(req));
+
+	TEST_RES(recv(sk_icmp, &pkt, sizeof(pkt), MSG_DONTWAIT),
+		 _ret == sizeof(pkt) && pkt.ip.ttl == 7 &&
+			 pkt.ip.tot_len == htons(sizeof(pkt)) &&
+			 pkt.icmp.type == ICMP_ECHO &&
+			 pkt.icmp.un.echo.sequence == htons(3));
+	TEST_RES(recv(sk_icmp, &pkt, sizeof(pkt), MSG_DONTWAIT),
+		 _ret == sizeof(pkt) && pkt.icmp.type == ICMP_ECHOREPLY &&
+			 pkt.icmp.un.echo.sequence == htons(3));
+
+	// The IP header must be complete.
+	TEST_ERRNO(sendto(sk_raw, &req, sizeof(req.ip) - 1, 0,
+			  (struct sockaddr *)&lo_addr, sizeof(lo_addr)),
+		   EINVAL);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(sk_icmp));
+	CHECK(close(sk_raw));
+}
+END_SETUP()
@@ -0,0 +1,181 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#include <unistd.h>
+#include <sys/socket.h>
+#include <netinet/in.h>
+#include <arpa/inet.h>
+#include <fcntl.h>
+
+#include "../common/test.h"
+
+#define NR_FLOWS 32
+
+static struct sockaddr_in addr;
+static socklen_t addrlen = sizeof(addr);
+
+FN_SETUP(init)
+{
+	addr.sin_family = AF_INET;
+	CHECK(inet_aton("127.0.0.1", &addr.sin_addr));
+}
+END_SETUP()
+
+static int new_reuseport_socket(int type, int reuseport)
+{
+	int sk = CHECK(socket(AF_INET, type | SOCK_NONBLOCK, 0));
+
+	CHECK(setsockopt(sk, SOL_SOCKET, SO_REUSEPORT, &reuseport,
+			 sizeof(reuseport)));
+
+	return sk;
+}
+
+FN_TEST(getsockopt)
+{
+	int sk = new_reuseport_socket(SOCK_STREAM, 1);
+	int option;
+	socklen_t option_len = sizeof(option);
+
+	TEST_RES(getsockopt(sk, SOL_SOCKET, SO_REUSEPORT, &option,
+			    &option_len),
+		 option == 1 && option_len == sizeof(option));
+
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(bind_without_reuseport)
+{
+	int sk1 = new_reuseport_socket(SOCK_DGRAM, 1);
+	int sk2 = new_reuseport_socket(SOCK_DGRAM, 0);
+	int sk3 = new_reuseport_socket(SOCK_DGRAM, 1);
+
+	addr.sin_port = 0;
+	TEST_SUCC(bind(sk1, (struct sockaddr *)&addr, addrlen));
+	TEST_SUCC(getsockname(sk1, (struct sockaddr *)&addr, &addrlen));
+
+	TEST_ERRNO(bind(sk2, (struct sockaddr *)&addr, addrlen), EADDRINUSE);
+	TEST_SUCC(bind(sk3, (struct sockaddr *)&addr, addrlen));
+
+	TEST_SUCC(close(sk1));
+	TEST_SUCC(close(sk2));
+	TEST_SUCC(close(sk3));
+}
+END_TEST()
+
+FN_TEST(disable_reuseport_after_bound)
+{
+	int sk1 = new_reuseport_socket(SOCK_DGRAM, 1);
+	int sk2 = new_reuseport_socket(SOCK_DGRAM, 1);
+	int option = 0;
+
+	addr.sin_port = 0;
+	TEST_SUCC(bind(sk1, (struct sockaddr *)&addr, addrlen));
+	TEST_SUCC(getsockname(sk1, (struct sockaddr *)&addr, &addrlen));
+
+	TEST_SUCC(setsockopt(sk1, SOL_SOCKET, SO_REUSEPORT, &option,
+			     sizeof(option)));
+	TEST_ERRNO(bind(sk2, (struct sockaddr *)&addr, addrlen), EADDRINUSE);
+
+	TEST_SUCC(close(sk1));
+	TEST_SUCC(close(sk2));
+}
+END_TEST()
+
+static int accept_all(int sk_listen)
+{
+	int count = 0;
+	int sk;
+
+	while ((sk = accept(sk_listen, NULL, NULL)) >= 0) {
+		close(sk);
+		++count;
+	}
+	if (errno != EAGAIN)
+		return -1;
+
+	errno = 0;
+	return count;
+}
+
+FN_TEST(tcp_listener_group)
+{
+	int sk_listen1 = new_reuseport_socket(SOCK_STREAM, 1);
+	int sk_listen2 = new_reuseport_socket(SOCK_STREAM, 1);
+	int sk_listen3 = new_reuseport_socket(SOCK_STREAM, 0);
+	int sk_clients[NR_FLOWS];
+	int i, count1, count2;
+
+	addr.sin_port = 0;
+	TEST_SUCC(bind(sk_listen1, (struct sockaddr *)&addr, addrlen));
+	TEST_SUCC(getsockname(sk_listen1, (struct sockaddr *)&addr, &addrlen));
+	TEST_SUCC(bind(sk_listen2, (struct sockaddr *)&addr, addrlen));
+	TEST_ERRNO(bind(sk_listen3, (struct sockaddr *)&addr, addrlen),
+		   EADDRINUSE);
+
+	TEST_SUCC(listen(sk_listen1, NR_FLOWS));
+	TEST_SUCC(listen(sk_listen2, NR_FLOWS));
+
+	for (i = 0; i < NR_FLOWS; ++i) {
+		sk_clients[i] = TEST_SUCC(socket(AF_INET, SOCK_STREAM, 0));
+		TEST_SUCC(connect(sk_clients[i], (struct sockaddr *)&addr,
+				  addrlen));
+	}
+
+	// The connections are distributed between the two listeners.
+	count1 = TEST_RES(accept_all(sk_listen1), _ret > 0);
+	count2 = TEST_RES(accept_all(sk_listen2), _ret > 0);
+	TEST_RES(count1 + count2, _ret == NR_FLOWS);
+
+	for (i = 0; i < NR_FLOWS; ++i)
+		TEST_SUCC(close(sk_clients[i]));
+
+	TEST_SUCC(close(sk_listen1));
+	TEST_SUCC(close(sk_listen2));
+	TEST_SUCC(close(sk_listen3));
+}
+END_TEST()
+
+static int recv_all(int sk)
+{
+	int count = 0;
+	char buf[1];
+
+	while (recv(sk, buf, sizeof(buf), 0) >= 0)
+		++count;
+	if (errno != EAGAIN)
+		return -1;
+
+	errno = 0;
+	return count;
+}
+
+FN_TEST(udp_socket_group)
+{
+	int sk_server1 = new_reuseport_socket(SOCK_DGRAM, 1);
+	int sk_server2 = new_reuseport_socket(SOCK_DGRAM, 1);
+	int sk_client;
+	int i, count1, count2;
+
+	addr.sin_port = 0;
+	TEST_SUCC(bind(sk_server1, (struct sockaddr *)&addr, addrlen));
+	TEST_SUCC(getsockname(sk_server1, (struct sockaddr *)&addr, &addrlen));
+	TEST_SUCC(bind(sk_server2, (struct sockaddr *)&addr, addrlen));
+
+	for (i = 0; i < NR_FLOWS; ++i) {
+		sk_client = TEST_SUCC(socket(AF_INET, SOCK_DGRAM, 0));
+		TEST_RES(sendto(sk_client, "x", 1, 0, (struct sockaddr *)&addr,
+				addrlen),
+			 _ret == 1);
+		TEST_SUCC(close(sk_client));
+	}
+
+	// Each datagram is delivered to exactly one of the two sockets.
+	count1 = TEST_RES(recv_all(sk_server1), _ret > 0);
+	count2 = TEST_RES(recv_all(sk_server2), _ret > 0);
+	TEST_RES(count1 + count2, _ret == NR_FLOWS);
+
+	TEST_SUCC(close(sk_server1));
+	TEST_SUCC(close(sk_server2));
+}
+END_TEST()
@@ -24,13 +24,30 @@ sleep 0.2
 ./tcp_err
 ./tcp_poll
 ./tcp_reuseaddr
+./reuseport
 ./udp_broadcast
+./udp_multicast
+./ping_socket
+./raw_socket
+./packet_socket
+./iptables
+./tun
 ./udp_err
+./ipv6
 ./unix_stream_err
 ./unix_seqpacket_err
 ./unix_datagram_err
 ./sendmmsg
+./zerocopy
+./timestamping
+./bind_device
+./iface_ioctl
+./ktls
+./af_alg
+./bpf
 
 ./netlink_route
 ./rtnl_err
 ./uevent_err
+./uevent_synth
+./wireguard
@@ -127,4 +127,112 @@ FN_TEST(sendmmsg)
 				 (strcmp(buffers[i], recv_buffers[i]) == 0));
 	}
 }
-END_TEST()
\ No newline at end of file
+END_TEST()
+
+static void send_messages(int count)
+{
+	char buffer[MAX_BUFFER_SIZE];
+
+	for (int i = 0; i < count; ++i) {
+		sprintf(buffer, "The %d Hello from sender!", i);
+		CHECK(send(sk_sender, buffer, strlen(buffer), 0));
+	}
+}
+
+static struct mmsghdr recv_msgs[NUM_MESSAGES + 1];
+static struct iovec recv_iovs[NUM_MESSAGES + 1];
+static char recv_buffers[NUM_MESSAGES + 1][MAX_BUFFER_SIZE];
+static struct sockaddr_in recv_addrs[NUM_MESSAGES + 1];
+
+static void init_recv_msgs(void)
+{
+	memset(recv_msgs, 0, sizeof(recv_msgs));
+	memset(recv_buffers, 0, sizeof(recv_buffers));
+
+	for (int i = 0; i < NUM_MESSAGES + 1; ++i) {
+		recv_iovs[i].iov_base = recv_buffers[i];
+		recv_iovs[i].iov_len = MAX_BUFFER_SIZE;
+
+		recv_msgs[i].msg_hdr.msg_iov = &recv_iovs[i];
+		recv_msgs[i].msg_hdr.msg_iovlen = 1;
+		recv_msgs[i].msg_hdr.msg_name = &recv_addrs[i];
+		recv_msgs[i].msg_hdr.msg_namelen = sizeof(recv_addrs[i]);
+	}
+}
+
+static bool check_recv_msgs(int count)
+{
+	char buffer[MAX_BUFFER_SIZE];
+
+	for (int i = 0; i < count; ++i) {
+		sprintf(buffer, "The %d Hello from sender!", i);
+
+		if (recv_msgs[i].msg_len != strlen(buffer) ||
+		    strcmp(recv_buffers[i], buffer) != 0)
+			return false;
+
+		if (recv_msgs[i].msg_hdr.msg_namelen != sockaddr_len ||
+		    recv_addrs[i].sin_port != sender_addr.sin_port)
+			return false;
+	}
+
+	return true;
+}
+
+FN_TEST(recvmmsg)
+{
+	init_recv_msgs();
+	TEST_ERRNO(recvmmsg(sk_receiver, recv_msgs, NUM_MESSAGES + 1, 0, NULL),
+		   EAGAIN);
+
+	send_messages(NUM_MESSAGES);
+
+	init_recv_msgs();
+	TEST_RES(recvmmsg(sk_receiver, recv_msgs, NUM_MESSAGES + 1, 0, NULL),
+		 _ret == NUM_MESSAGES && check_recv_msgs(NUM_MESSAGES));
+}
+END_TEST()
+
+FN_TEST(recvmmsg_waitforone)
+{
+	int flags = CHECK(fcntl(sk_receiver, F_GETFL, 0));
+
+	send_messages(NUM_MESSAGES - 1);
+
+	// The call does not block after the first message is received.
+	TEST_SUCC(fcntl(sk_receiver, F_SETFL, flags & ~O_NONBLOCK));
+	init_recv_msgs();
+	TEST_RES(recvmmsg(sk_receiver, recv_msgs, NUM_MESSAGES + 1,
+			  MSG_WAITFORONE, NULL),
+		 _ret == NUM_MESSAGES - 1 && check_recv_msgs(NUM_MESSAGES - 1));
+	TEST_SUCC(fcntl(sk_receiver, F_SETFL, flags));
+}
+END_TEST()
+
+FN_TEST(recvmmsg_timeout)
+{
+	struct timespec timeout = { .tv_sec = 0, .tv_nsec = 1000000000 };
+
+	init_recv_msgs();
+	TEST_ERRNO(recvmmsg(sk_receiver, recv_msgs, NUM_MESSAGES + 1, 0,
+			    &timeout),
+		   EINVAL);
+
+	send_messages(NUM_MESSAGES);
+
+	// The timeout expires after the first message is received.
+	timeout.tv_nsec = 0;
+	init_recv_msgs();
+	TEST_RES(recvmmsg(sk_receiver, recv_msgs, NUM_MESSAGES + 1, 0,
+			  &timeout),
+		 _ret == 1 && check_recv_msgs(1) && timeout.tv_sec == 0 &&
+			 timeout.tv_nsec == 0);
+
+	timeout.tv_sec = 1000;
+	init_recv_msgs();
+	TEST_RES(recvmmsg(sk_receiver, recv_msgs, NUM_MESSAGES + 1, 0,
+			  &timeout),
+		 _ret == NUM_MESSAGES - 1 && timeout.tv_sec <= 1000 &&
+			 timeout.tv_sec >= 999);
+}
+END_TEST()
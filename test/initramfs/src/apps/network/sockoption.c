@@ -2,6 +2,7 @@
 
 #include <stdio.h>
 #include <stdlib.h>
+#include <string.h>
 #include <sys/socket.h>
 #include <netinet/in.h>
 #include <netinet/tcp.h>
@@ -282,6 +283,89 @@ FN_TEST(keepidle)
 }
 END_TEST()
 
+FN_TEST(keepintvl_and_keepcnt)
+{
+	int value;
+	socklen_t value_len = sizeof(value);
+
+	// 1. Check default values
+	refresh_connection();
+	TEST_RES(getsockopt(sk_connected, IPPROTO_TCP, TCP_KEEPINTVL, &value,
+			    &value_len),
+		 value == 75);
+	TEST_RES(getsockopt(sk_connected, IPPROTO_TCP, TCP_KEEPCNT, &value,
+			    &value_len),
+		 value == 9);
+
+	// 2. Set and get values
+	value = 10;
+	TEST_SUCC(setsockopt(sk_connected, IPPROTO_TCP, TCP_KEEPINTVL, &value,
+			     sizeof(value)));
+	value = 3;
+	TEST_SUCC(setsockopt(sk_connected, IPPROTO_TCP, TCP_KEEPCNT, &value,
+			     sizeof(value)));
+	TEST_RES(getsockopt(sk_connected, IPPROTO_TCP, TCP_KEEPINTVL, &value,
+			    &value_len),
+		 value == 10);
+	TEST_RES(getsockopt(sk_connected, IPPROTO_TCP, TCP_KEEPCNT, &value,
+			    &value_len),
+		 value == 3);
+
+	// 3. Set invalid values
+	value = 0;
+	TEST_ERRNO(setsockopt(sk_connected, IPPROTO_TCP, TCP_KEEPINTVL, &value,
+			      sizeof(value)),
+		   EINVAL);
+	value = 128;
+	TEST_ERRNO(setsockopt(sk_connected, IPPROTO_TCP, TCP_KEEPCNT, &value,
+			      sizeof(value)),
+		   EINVAL);
+}
+END_TEST()
+
+FN_TEST(congestion)
+{
+	char name[16];
+	socklen_t name_len = sizeof(name);
+
+	// 1. Check default values
+	refresh_connection();
+	TEST_RES(getsockopt(sk_unbound, IPPROTO_TCP, TCP_CONGESTION, name,
+			    &name_len),
+		 strcmp(name, "cubic") == 0);
+	TEST_RES(getsockopt(sk_accepted, IPPROTO_TCP, TCP_CONGESTION, name,
+			    &name_len),
+		 strcmp(name, "cubic") == 0);
+
+	// 2. Set and get values
+	TEST_SUCC(setsockopt(sk_connected, IPPROTO_TCP, TCP_CONGESTION, "reno",
+			     strlen("reno")));
+	TEST_RES(getsockopt(sk_connected, IPPROTO_TCP, TCP_CONGESTION, name,
+			    &name_len),
+		 strcmp(name, "reno") == 0);
+	TEST_SUCC(setsockopt(sk_connected, IPPROTO_TCP, TCP_CONGESTION,
+			     "cubic", sizeof("cubic")));
+	TEST_RES(getsockopt(sk_connected, IPPROTO_TCP, TCP_CONGESTION, name,
+			    &name_len),
+		 strcmp(name, "cubic") == 0);
+
+	// 3. Set unknown values
+	TEST_ERRNO(setsockopt(sk_connected, IPPROTO_TCP, TCP_CONGESTION,
+			      "unknown", strlen("unknown")),
+		   ENOENT);
+
+	// 4. Inherit values from the listening socket
+	TEST_SUCC(setsockopt(sk_listen, IPPROTO_TCP, TCP_CONGESTION, "reno",
+			     strlen("reno")));
+	refresh_connection();
+	TEST_RES(getsockopt(sk_accepted, IPPROTO_TCP, TCP_CONGESTION, name,
+			    &name_len),
+		 strcmp(name, "reno") == 0);
+	TEST_SUCC(setsockopt(sk_listen, IPPROTO_TCP, TCP_CONGESTION, "cubic",
+			     strlen("cubic")));
+}
+END_TEST()
+
 FN_TEST(ip_tos)
 {
 	int tos;
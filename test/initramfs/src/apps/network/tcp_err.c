@@ -5,6 +5,7 @@
 #include <sys/socket.h>
 #include <sys/poll.h>
 #include <netinet/in.h>
+#include <netinet/tcp.h>
 #include <arpa/inet.h>
 #include <fcntl.h>
 
@@ -778,3 +779,77 @@ FN_TEST(listen_close)
 	TEST_SUCC(close(sk_connect));
 }
 END_TEST()
+
+FN_TEST(defer_accept)
+{
+	int sk_listen;
+	int sk_connect;
+	int sk_accept;
+	int seconds = 10;
+	char buf[1] = { 'a' };
+
+	sk_addr.sin_port = htons(0x4322);
+
+	sk_listen = TEST_SUCC(socket(PF_INET, SOCK_STREAM | SOCK_NONBLOCK, 0));
+	TEST_SUCC(setsockopt(sk_listen, IPPROTO_TCP, TCP_DEFER_ACCEPT,
+			     &seconds, sizeof(seconds)));
+	TEST_SUCC(
+		bind(sk_listen, (struct sockaddr *)&sk_addr, sizeof(sk_addr)));
+	TEST_SUCC(listen(sk_listen, 10));
+
+	sk_connect = TEST_SUCC(socket(PF_INET, SOCK_STREAM, 0));
+	TEST_SUCC(connect(sk_connect, (struct sockaddr *)&sk_addr,
+			  sizeof(sk_addr)));
+
+	// Test: The connection is not accepted until data arrives
+	TEST_ERRNO(accept(sk_listen, NULL, NULL), EAGAIN);
+
+	TEST_RES(send(sk_connect, buf, 1, 0), _ret == 1);
+	sk_accept = TEST_SUCC(accept(sk_listen, NULL, NULL));
+	TEST_RES(recv(sk_accept, buf, 1, 0), _ret == 1 && buf[0] == 'a');
+
+	TEST_SUCC(close(sk_accept));
+	TEST_SUCC(close(sk_connect));
+	TEST_SUCC(close(sk_listen));
+}
+END_TEST()
+
+FN_TEST(sendto_fastopen)
+{
+	int sk_listen;
+	int sk_connect;
+	int sk_accept;
+	char buf[1] = { 'b' };
+
+	sk_addr.sin_port = htons(0x4323);
+
+	sk_listen = TEST_SUCC(socket(PF_INET, SOCK_STREAM, 0));
+	TEST_SUCC(
+		bind(sk_listen, (struct sockaddr *)&sk_addr, sizeof(sk_addr)));
+	TEST_SUCC(listen(sk_listen, 10));
+
+	// TCP Fast Open is not supported, which matches Linux when the client
+	// side is disabled (i.e., bit 0 of `net.ipv4.tcp_fastopen` is cleared).
+
+	// Test: `MSG_FASTOPEN` is rejected without connecting the socket
+	sk_connect = TEST_SUCC(socket(PF_INET, SOCK_STREAM, 0));
+	TEST_ERRNO(sendto(sk_connect, buf, 1, MSG_FASTOPEN,
+			  (struct sockaddr *)&sk_addr, sizeof(sk_addr)),
+		   EOPNOTSUPP);
+	TEST_ERRNO(send(sk_connect, buf, 1, 0), EPIPE);
+
+	// Test: `MSG_FASTOPEN` is rejected even if the socket is connected
+	TEST_SUCC(connect(sk_connect, (struct sockaddr *)&sk_addr,
+			  sizeof(sk_addr)));
+	TEST_ERRNO(sendto(sk_connect, buf, 1, MSG_FASTOPEN, NULL, 0),
+		   EOPNOTSUPP);
+
+	sk_accept = TEST_SUCC(accept(sk_listen, NULL, NULL));
+	TEST_RES(send(sk_connect, buf, 1, 0), _ret == 1);
+	TEST_RES(recv(sk_accept, buf, 1, 0), _ret == 1 && buf[0] == 'b');
+
+	TEST_SUCC(close(sk_accept));
+	TEST_SUCC(close(sk_connect));
+	TEST_SUCC(close(sk_listen));
+}
+END_TEST()
@@ -0,0 +1,308 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <unistd.h>
+#include <poll.h>
+#include <string.h>
+#include <time.h>
+#include <sys/socket.h>
+#include <netinet/in.h>
+#include <netinet/tcp.h>
+#include <arpa/inet.h>
+#include <linux/errqueue.h>
+#include <linux/net_tstamp.h>
+
+#include "../common/test.h"
+
+#ifndef SOF_TIMESTAMPING_OPT_RX_FILTER
+#define SOF_TIMESTAMPING_OPT_RX_FILTER (1 << 17)
+#endif
+
+static struct sockaddr_in addr;
+static socklen_t addrlen = sizeof(addr);
+
+static struct timespec ts_before;
+static struct timespec ts_after;
+
+static struct msghdr msg;
+
+#define TX_FLAGS                                                     \
+	(SOF_TIMESTAMPING_TX_SOFTWARE | SOF_TIMESTAMPING_SOFTWARE |  \
+	 SOF_TIMESTAMPING_OPT_ID | SOF_TIMESTAMPING_OPT_TSONLY)
+
+FN_SETUP(init)
+{
+	addr.sin_family = AF_INET;
+	CHECK(inet_aton("127.0.0.1", &addr.sin_addr));
+}
+END_SETUP()
+
+static int set_option(int sk, int name, int value)
+{
+	return setsockopt(sk, SOL_SOCKET, name, &value, sizeof(value));
+}
+
+static int get_option(int sk, int name)
+{
+	int value;
+	socklen_t len = sizeof(value);
+
+	if (getsockopt(sk, SOL_SOCKET, name, &value, &len) < 0)
+		return -1;
+
+	return value;
+}
+
+static int recv_msg(int sk, int flags)
+{
+	static char buf[16];
+	static char control[256];
+	static struct iovec iov = { .iov_base = buf, .iov_len = sizeof(buf) };
+	int ret;
+
+	memset(&msg, 0, sizeof(msg));
+	msg.msg_iov = &iov;
+	msg.msg_iovlen = 1;
+	msg.msg_control = control;
+	msg.msg_controllen = sizeof(control);
+
+	ret = recvmsg(sk, &msg, flags);
+	if (ret < 0)
+		return ret;
+
+	if (clock_gettime(CLOCK_REALTIME, &ts_after) < 0)
+		return -1;
+
+	return ret;
+}
+
+static void *find_cmsg(int level, int type, size_t len)
+{
+	struct cmsghdr *cmsg;
+
+	for (cmsg = CMSG_FIRSTHDR(&msg); cmsg != NULL;
+	     cmsg = CMSG_NXTHDR(&msg, cmsg))
+		if (cmsg->cmsg_level == level && cmsg->cmsg_type == type &&
+		    cmsg->cmsg_len == CMSG_LEN(len))
+			return CMSG_DATA(cmsg);
+
+	return NULL;
+}
+
+static long long to_ns(const struct timespec *ts)
+{
+	return ts->tv_sec * 1000000000LL + ts->tv_nsec;
+}
+
+// Checks that the timestamp is taken between `ts_before` and `ts_after`. The
+// timestamp may be rounded down to `granularity` nanoseconds.
+static int check_time(const struct timespec *ts, long long granularity)
+{
+	return to_ns(ts) >= to_ns(&ts_before) / granularity * granularity &&
+	       to_ns(ts) <= to_ns(&ts_after);
+}
+
+static int check_timestamp(void)
+{
+	struct timeval *tv =
+		find_cmsg(SOL_SOCKET, SCM_TIMESTAMP, sizeof(struct timeval));
+	struct timespec ts;
+
+	if (tv == NULL)
+		return 0;
+
+	ts.tv_sec = tv->tv_sec;
+	ts.tv_nsec = tv->tv_usec * 1000;
+	return check_time(&ts, 1000);
+}
+
+static int check_timestampns(void)
+{
+	struct timespec *ts = find_cmsg(SOL_SOCKET, SCM_TIMESTAMPNS,
+					sizeof(struct timespec));
+
+	return ts != NULL && check_time(ts, 1);
+}
+
+static int check_timestamping(void)
+{
+	struct scm_timestamping *tss = find_cmsg(
+		SOL_SOCKET, SCM_TIMESTAMPING, sizeof(struct scm_timestamping));
+
+	return tss != NULL && check_time(&tss->ts[0], 1) &&
+	       tss->ts[2].tv_sec == 0 && tss->ts[2].tv_nsec == 0;
+}
+
+static int check_tx_err(__u32 key)
+{
+	struct sock_extended_err *serr =
+		find_cmsg(SOL_IP, IP_RECVERR,
+			  sizeof(struct sock_extended_err) +
+				  sizeof(struct sockaddr_in));
+
+	return serr != NULL && serr->ee_errno == ENOMSG &&
+	       serr->ee_origin == SO_EE_ORIGIN_TIMESTAMPING &&
+	       serr->ee_info == SCM_TSTAMP_SND && serr->ee_data == key;
+}
+
+static short poll_err(int sk)
+{
+	// `POLLERR` is always reported, even if it is not requested.
+	struct pollfd pfd = { .fd = sk, .events = 0 };
+
+	if (poll(&pfd, 1, 1000) < 0)
+		return -1;
+
+	return pfd.revents;
+}
+
+FN_TEST(timestamp_options)
+{
+	int sk = TEST_SUCC(socket(AF_INET, SOCK_DGRAM, 0));
+	int flags = SOF_TIMESTAMPING_SOFTWARE | SOF_TIMESTAMPING_RX_SOFTWARE;
+	struct so_timestamping so_ts;
+	socklen_t so_ts_len = sizeof(so_ts);
+
+	TEST_RES(get_option(sk, SO_TIMESTAMP), _ret == 0);
+	TEST_RES(get_option(sk, SO_TIMESTAMPNS), _ret == 0);
+	TEST_RES(get_option(sk, SO_TIMESTAMPING), _ret == 0);
+
+	// `SO_TIMESTAMP` and `SO_TIMESTAMPNS` override each other.
+	TEST_SUCC(set_option(sk, SO_TIMESTAMPNS, 1));
+	TEST_RES(get_option(sk, SO_TIMESTAMP), _ret == 0);
+	TEST_RES(get_option(sk, SO_TIMESTAMPNS), _ret == 1);
+	TEST_SUCC(set_option(sk, SO_TIMESTAMP, 1));
+	TEST_RES(get_option(sk, SO_TIMESTAMP), _ret == 1);
+	TEST_RES(get_option(sk, SO_TIMESTAMPNS), _ret == 0);
+	TEST_SUCC(set_option(sk, SO_TIMESTAMPNS, 0));
+	TEST_RES(get_option(sk, SO_TIMESTAMP), _ret == 0);
+
+	TEST_ERRNO(set_option(sk, SO_TIMESTAMPING, 1 << 30), EINVAL);
+
+	TEST_SUCC(set_option(sk, SO_TIMESTAMPING, flags));
+	TEST_RES(get_option(sk, SO_TIMESTAMPING), _ret == flags);
+	TEST_RES(getsockopt(sk, SOL_SOCKET, SO_TIMESTAMPING, &so_ts,
+			    &so_ts_len),
+		 so_ts_len == sizeof(so_ts) && so_ts.flags == flags &&
+			 so_ts.bind_phc == 0);
+
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(udp_rx_timestamp)
+{
+	int sk_send = TEST_SUCC(socket(AF_INET, SOCK_DGRAM, 0));
+	int sk_recv = TEST_SUCC(socket(AF_INET, SOCK_DGRAM, 0));
+
+	addr.sin_port = 0;
+	TEST_SUCC(bind(sk_recv, (struct sockaddr *)&addr, addrlen));
+	TEST_SUCC(getsockname(sk_recv, (struct sockaddr *)&addr, &addrlen));
+	TEST_SUCC(connect(sk_send, (struct sockaddr *)&addr, addrlen));
+
+	TEST_RES(send(sk_send, "abc", 3, 0), _ret == 3);
+	TEST_RES(recv_msg(sk_recv, 0), _ret == 3 && msg.msg_controllen == 0);
+
+	TEST_SUCC(set_option(sk_recv, SO_TIMESTAMP, 1));
+	CHECK(clock_gettime(CLOCK_REALTIME, &ts_before));
+	TEST_RES(send(sk_send, "abc", 3, 0), _ret == 3);
+	TEST_RES(recv_msg(sk_recv, 0), _ret == 3 && check_timestamp());
+
+	TEST_SUCC(set_option(sk_recv, SO_TIMESTAMPNS, 1));
+	CHECK(clock_gettime(CLOCK_REALTIME, &ts_before));
+	TEST_RES(send(sk_send, "abc", 3, 0), _ret == 3);
+	TEST_RES(recv_msg(sk_recv, 0), _ret == 3 && check_timestampns() &&
+						!check_timestamp());
+
+	TEST_SUCC(set_option(sk_recv, SO_TIMESTAMPNS, 0));
+	TEST_SUCC(set_option(sk_recv, SO_TIMESTAMPING,
+			     SOF_TIMESTAMPING_SOFTWARE |
+				     SOF_TIMESTAMPING_RX_SOFTWARE));
+	CHECK(clock_gettime(CLOCK_REALTIME, &ts_before));
+	TEST_RES(send(sk_send, "abc", 3, 0), _ret == 3);
+	TEST_RES(recv_msg(sk_recv, 0), _ret == 3 && check_timestamping() &&
+						!check_timestampns());
+
+	// Received packets are not timestamped if they are filtered out.
+	TEST_SUCC(set_option(sk_recv, SO_TIMESTAMPING,
+			     SOF_TIMESTAMPING_SOFTWARE |
+				     SOF_TIMESTAMPING_OPT_RX_FILTER));
+	TEST_RES(send(sk_send, "abc", 3, 0), _ret == 3);
+	TEST_RES(recv_msg(sk_recv, 0), _ret == 3 && msg.msg_controllen == 0);
+
+	TEST_SUCC(close(sk_send));
+	TEST_SUCC(close(sk_recv));
+}
+END_TEST()
+
+FN_TEST(udp_tx_timestamp)
+{
+	int sk_send = TEST_SUCC(socket(AF_INET, SOCK_DGRAM, 0));
+	int sk_recv = TEST_SUCC(socket(AF_INET, SOCK_DGRAM, 0));
+
+	addr.sin_port = 0;
+	TEST_SUCC(bind(sk_recv, (struct sockaddr *)&addr, addrlen));
+	TEST_SUCC(getsockname(sk_recv, (struct sockaddr *)&addr, &addrlen));
+	TEST_SUCC(connect(sk_send, (struct sockaddr *)&addr, addrlen));
+
+	TEST_RES(send(sk_send, "abc", 3, 0), _ret == 3);
+	TEST_ERRNO(recv_msg(sk_send, MSG_ERRQUEUE), EAGAIN);
+
+	TEST_SUCC(set_option(sk_send, SO_TIMESTAMPING, TX_FLAGS));
+	CHECK(clock_gettime(CLOCK_REALTIME, &ts_before));
+	TEST_RES(send(sk_send, "abc", 3, 0), _ret == 3);
+	TEST_RES(send(sk_send, "def", 3, 0), _ret == 3);
+
+	// Each datagram takes one key.
+	TEST_RES(poll_err(sk_send), _ret == POLLERR);
+	TEST_RES(recv_msg(sk_send, MSG_ERRQUEUE),
+		 _ret == 0 && check_timestamping() && check_tx_err(0));
+	TEST_RES(recv_msg(sk_send, MSG_ERRQUEUE),
+		 _ret == 0 && check_timestamping() && check_tx_err(1));
+	TEST_ERRNO(recv_msg(sk_send, MSG_ERRQUEUE), EAGAIN);
+
+	TEST_SUCC(close(sk_send));
+	TEST_SUCC(close(sk_recv));
+}
+END_TEST()
+
+FN_TEST(tcp_timestamp)
+{
+	int sk_listen = TEST_SUCC(socket(AF_INET, SOCK_STREAM, 0));
+	int sk_connect = TEST_SUCC(socket(AF_INET, SOCK_STREAM, 0));
+	int sk_accept;
+	int one = 1;
+
+	addr.sin_port = 0;
+	TEST_SUCC(bind(sk_listen, (struct sockaddr *)&addr, addrlen));
+	TEST_SUCC(getsockname(sk_listen, (struct sockaddr *)&addr, &addrlen));
+	TEST_SUCC(listen(sk_listen, 1));
+	TEST_SUCC(connect(sk_connect, (struct sockaddr *)&addr, addrlen));
+	sk_accept = TEST_SUCC(accept(sk_listen, NULL, NULL));
+
+	TEST_SUCC(setsockopt(sk_connect, IPPROTO_TCP, TCP_NODELAY, &one,
+			     sizeof(one)));
+	TEST_SUCC(set_option(sk_connect, SO_TIMESTAMPING, TX_FLAGS));
+	TEST_SUCC(set_option(sk_accept, SO_TIMESTAMPNS, 1));
+
+	CHECK(clock_gettime(CLOCK_REALTIME, &ts_before));
+	TEST_RES(send(sk_connect, "abc", 3, 0), _ret == 3);
+	TEST_RES(poll_err(sk_connect), _ret == POLLERR);
+	TEST_RES(recv_msg(sk_accept, 0), _ret == 3 && check_timestampns());
+
+	// Each byte takes one key.
+	TEST_RES(send(sk_connect, "def", 3, 0), _ret == 3);
+	TEST_RES(recv_msg(sk_accept, 0), _ret == 3 && check_timestampns());
+	TEST_RES(recv_msg(sk_connect, MSG_ERRQUEUE),
+		 _ret == 0 && check_timestamping() && check_tx_err(2));
+	TEST_RES(poll_err(sk_connect), _ret == POLLERR);
+	TEST_RES(recv_msg(sk_connect, MSG_ERRQUEUE),
+		 _ret == 0 && check_timestamping() && check_tx_err(5));
+	TEST_ERRNO(recv_msg(sk_connect, MSG_ERRQUEUE), EAGAIN);
+
+	TEST_SUCC(close(sk_listen));
+	TEST_SUCC(close(sk_connect));
+	TEST_SUCC(close(sk_accept));
+}
+END_TEST()
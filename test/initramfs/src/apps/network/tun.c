@@ -0,0 +1,164 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+#include <fcntl.h>
+#include <poll.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+#include <sys/ioctl.h>
+#include <net/if.h>
+#include <linux/if_tun.h>
+
+#include "../common/test.h"
+
+#define TUN_PATH "/dev/net/tun"
+
+static int set_iff(int fd, const char *name, short flags, char *out_name)
+{
+	struct ifreq ifr;
+	int ret;
+
+	memset(&ifr, 0, sizeof(ifr));
+	strncpy(ifr.ifr_name, name, IFNAMSIZ - 1);
+	ifr.ifr_flags = flags;
+
+	ret = ioctl(fd, TUNSETIFF, &ifr);
+	if (ret >= 0 && out_name != NULL)
+		strcpy(out_name, ifr.ifr_name);
+	return ret;
+}
+
+static int set_queue(int fd, short flags)
+{
+	struct ifreq ifr;
+
+	memset(&ifr, 0, sizeof(ifr));
+	ifr.ifr_flags = flags;
+
+	return ioctl(fd, TUNSETQUEUE, &ifr);
+}
+
+static const char *iface_path(const char *name)
+{
+	static char path[64];
+
+	snprintf(path, sizeof(path), "/sys/class/net/%s", name);
+	return path;
+}
+
+FN_TEST(unattached)
+{
+	struct ifreq ifr;
+	char buf[16];
+	int fd;
+
+	fd = TEST_SUCC(open(TUN_PATH, O_RDWR | O_NONBLOCK));
+
+	TEST_ERRNO(read(fd, buf, sizeof(buf)), EBADFD);
+	TEST_ERRNO(write(fd, buf, sizeof(buf)), EBADFD);
+	TEST_ERRNO(ioctl(fd, TUNGETIFF, &ifr), EBADFD);
+
+	TEST_ERRNO(set_iff(fd, "", IFF_TUN | IFF_TAP, NULL), EINVAL);
+	TEST_ERRNO(set_iff(fd, "", 0, NULL), EINVAL);
+	TEST_ERRNO(set_iff(fd, "lo", IFF_TUN, NULL), EINVAL);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(features)
+{
+	unsigned int features;
+	int fd;
+
+	fd = TEST_SUCC(open(TUN_PATH, O_RDWR));
+
+	TEST_RES(ioctl(fd, TUNGETFEATURES, &features),
+		 (features & (IFF_TUN | IFF_TAP | IFF_NO_PI |
+			      IFF_MULTI_QUEUE)) ==
+			 (IFF_TUN | IFF_TAP | IFF_NO_PI | IFF_MULTI_QUEUE));
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(tun)
+{
+	char name[IFNAMSIZ];
+	struct ifreq ifr;
+	struct pollfd pfd;
+	char buf[16];
+	int fd, fd2;
+
+	fd = TEST_SUCC(open(TUN_PATH, O_RDWR | O_NONBLOCK));
+
+	TEST_RES(set_iff(fd, "", IFF_TUN | IFF_NO_PI, name),
+		 strcmp(name, "tun0") == 0);
+	TEST_SUCC(access(iface_path("tun0"), F_OK));
+	TEST_ERRNO(set_iff(fd, "tun0", IFF_TUN | IFF_NO_PI, NULL), EEXIST);
+
+	TEST_RES(ioctl(fd, TUNGETIFF, &ifr),
+		 strcmp(ifr.ifr_name, "tun0") == 0 &&
+			 ifr.ifr_flags == (IFF_TUN | IFF_NO_PI));
+
+	TEST_ERRNO(read(fd, buf, sizeof(buf)), EAGAIN);
+	pfd.fd = fd;
+	pfd.events = POLLIN | POLLOUT;
+	TEST_RES(poll(&pfd, 1, 0), _ret == 1 && pfd.revents == POLLOUT);
+
+	// A single-queue iface cannot be attached by another file.
+	fd2 = TEST_SUCC(open(TUN_PATH, O_RDWR));
+	TEST_ERRNO(set_iff(fd2, "tun0", IFF_TUN | IFF_NO_PI, NULL), EBUSY);
+	TEST_ERRNO(set_iff(fd2, "tun0", IFF_TAP | IFF_NO_PI, NULL), EINVAL);
+	TEST_ERRNO(set_queue(fd, IFF_DETACH_QUEUE), EINVAL);
+	TEST_SUCC(close(fd2));
+
+	TEST_SUCC(close(fd));
+	TEST_ERRNO(access(iface_path("tun0"), F_OK), ENOENT);
+}
+END_TEST()
+
+FN_TEST(multi_queue)
+{
+	char name[IFNAMSIZ];
+	int fd1, fd2;
+
+	fd1 = TEST_SUCC(open(TUN_PATH, O_RDWR));
+	fd2 = TEST_SUCC(open(TUN_PATH, O_RDWR));
+
+	TEST_RES(set_iff(fd1, "mq%d", IFF_TAP | IFF_MULTI_QUEUE, name),
+		 strcmp(name, "mq0") == 0);
+	TEST_ERRNO(set_iff(fd2, "mq0", IFF_TAP, NULL), EINVAL);
+	TEST_SUCC(set_iff(fd2, "mq0", IFF_TAP | IFF_MULTI_QUEUE, NULL));
+
+	TEST_ERRNO(set_queue(fd2, IFF_ATTACH_QUEUE), EINVAL);
+	TEST_SUCC(set_queue(fd2, IFF_DETACH_QUEUE));
+	TEST_ERRNO(set_queue(fd2, IFF_DETACH_QUEUE), EINVAL);
+	TEST_SUCC(set_queue(fd2, IFF_ATTACH_QUEUE));
+
+	// The iface is removed after all the queues are closed.
+	TEST_SUCC(close(fd1));
+	TEST_SUCC(access(iface_path("mq0"), F_OK));
+	TEST_SUCC(close(fd2));
+	TEST_ERRNO(access(iface_path("mq0"), F_OK), ENOENT);
+}
+END_TEST()
+
+FN_TEST(persist)
+{
+	int fd;
+
+	fd = TEST_SUCC(open(TUN_PATH, O_RDWR));
+	TEST_SUCC(set_iff(fd, "ptun", IFF_TUN, NULL));
+	TEST_SUCC(ioctl(fd, TUNSETPERSIST, 1));
+	TEST_SUCC(close(fd));
+	TEST_SUCC(access(iface_path("ptun"), F_OK));
+
+	fd = TEST_SUCC(open(TUN_PATH, O_RDWR));
+	TEST_SUCC(set_iff(fd, "ptun", IFF_TUN, NULL));
+	TEST_SUCC(ioctl(fd, TUNSETPERSIST, 0));
+	TEST_SUCC(close(fd));
+	TEST_ERRNO(access(iface_path("ptun"), F_OK), ENOENT);
+}
+END_TEST()
@@ -0,0 +1,146 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+#include <unistd.h>
+#include <sys/socket.h>
+#include <netinet/in.h>
+#include <arpa/inet.h>
+
+#include "../common/test.h"
+
+#define GROUP_ADDR "224.0.0.251"
+#define OTHER_GROUP_ADDR "224.0.0.252"
+#define LOCAL_ADDR "127.0.0.1"
+#define NONEXISTENT_ADDR "192.0.2.1"
+
+static int sk;
+static struct ip_mreq mreq;
+
+FN_SETUP(general)
+{
+	sk = CHECK(socket(PF_INET, SOCK_DGRAM, 0));
+
+	CHECK(inet_aton(GROUP_ADDR, &mreq.imr_multiaddr));
+	CHECK(inet_aton(LOCAL_ADDR, &mreq.imr_interface));
+}
+END_SETUP()
+
+FN_TEST(multicast_ttl)
+{
+	int ttl;
+	socklen_t len = sizeof(ttl);
+
+	TEST_RES(getsockopt(sk, SOL_IP, IP_MULTICAST_TTL, &ttl, &len),
+		 ttl == 1 && len == sizeof(ttl));
+
+	ttl = 5;
+	TEST_SUCC(setsockopt(sk, SOL_IP, IP_MULTICAST_TTL, &ttl, sizeof(ttl)));
+	TEST_RES(getsockopt(sk, SOL_IP, IP_MULTICAST_TTL, &ttl, &len),
+		 ttl == 5 && len == sizeof(ttl));
+
+	ttl = 256;
+	TEST_ERRNO(setsockopt(sk, SOL_IP, IP_MULTICAST_TTL, &ttl, sizeof(ttl)),
+		   EINVAL);
+
+	ttl = -1;
+	TEST_SUCC(setsockopt(sk, SOL_IP, IP_MULTICAST_TTL, &ttl, sizeof(ttl)));
+	TEST_RES(getsockopt(sk, SOL_IP, IP_MULTICAST_TTL, &ttl, &len),
+		 ttl == 1 && len == sizeof(ttl));
+}
+END_TEST()
+
+FN_TEST(multicast_loop)
+{
+	int loop;
+	socklen_t len = sizeof(loop);
+
+	TEST_RES(getsockopt(sk, SOL_IP, IP_MULTICAST_LOOP, &loop, &len),
+		 loop == 1 && len == sizeof(loop));
+
+	loop = 0;
+	TEST_SUCC(
+		setsockopt(sk, SOL_IP, IP_MULTICAST_LOOP, &loop, sizeof(loop)));
+	TEST_RES(getsockopt(sk, SOL_IP, IP_MULTICAST_LOOP, &loop, &len),
+		 loop == 0 && len == sizeof(loop));
+
+	loop = 1;
+	TEST_SUCC(
+		setsockopt(sk, SOL_IP, IP_MULTICAST_LOOP, &loop, sizeof(loop)));
+}
+END_TEST()
+
+FN_TEST(add_and_drop_membership)
+{
+	struct ip_mreq other_mreq = mreq;
+	struct ip_mreq any_mreq = mreq;
+
+	CHECK(inet_aton(OTHER_GROUP_ADDR, &other_mreq.imr_multiaddr));
+	any_mreq.imr_interface.s_addr = htonl(INADDR_ANY);
+
+	TEST_SUCC(setsockopt(sk, SOL_IP, IP_ADD_MEMBERSHIP, &mreq,
+			     sizeof(mreq)));
+	TEST_ERRNO(setsockopt(sk, SOL_IP, IP_ADD_MEMBERSHIP, &mreq,
+			      sizeof(mreq)),
+		   EADDRINUSE);
+
+	TEST_ERRNO(setsockopt(sk, SOL_IP, IP_DROP_MEMBERSHIP, &other_mreq,
+			      sizeof(other_mreq)),
+		   EADDRNOTAVAIL);
+
+	// The group is left on any interface if no interface is specified.
+	TEST_SUCC(setsockopt(sk, SOL_IP, IP_DROP_MEMBERSHIP, &any_mreq,
+			     sizeof(any_mreq)));
+	TEST_ERRNO(setsockopt(sk, SOL_IP, IP_DROP_MEMBERSHIP, &mreq,
+			      sizeof(mreq)),
+		   EADDRNOTAVAIL);
+}
+END_TEST()
+
+FN_TEST(add_membership_ip_mreqn)
+{
+	// The interface index of the loopback interface is 1.
+	struct ip_mreqn mreqn = { .imr_ifindex = 1 };
+
+	mreqn.imr_multiaddr = mreq.imr_multiaddr;
+
+	TEST_SUCC(setsockopt(sk, SOL_IP, IP_ADD_MEMBERSHIP, &mreqn,
+			     sizeof(mreqn)));
+	TEST_ERRNO(setsockopt(sk, SOL_IP, IP_ADD_MEMBERSHIP, &mreq,
+			      sizeof(mreq)),
+		   EADDRINUSE);
+	TEST_SUCC(setsockopt(sk, SOL_IP, IP_DROP_MEMBERSHIP, &mreqn,
+			     sizeof(mreqn)));
+}
+END_TEST()
+
+FN_TEST(add_membership_invalid)
+{
+	struct ip_mreq bad_mreq = mreq;
+	int value;
+	socklen_t len = sizeof(value);
+
+	TEST_ERRNO(setsockopt(sk, SOL_IP, IP_ADD_MEMBERSHIP, &mreq,
+			      sizeof(mreq) - 1),
+		   EINVAL);
+
+	CHECK(inet_aton(LOCAL_ADDR, &bad_mreq.imr_multiaddr));
+	TEST_ERRNO(setsockopt(sk, SOL_IP, IP_ADD_MEMBERSHIP, &bad_mreq,
+			      sizeof(bad_mreq)),
+		   EINVAL);
+
+	bad_mreq = mreq;
+	CHECK(inet_aton(NONEXISTENT_ADDR, &bad_mreq.imr_interface));
+	TEST_ERRNO(setsockopt(sk, SOL_IP, IP_ADD_MEMBERSHIP, &bad_mreq,
+			      sizeof(bad_mreq)),
+		   ENODEV);
+
+	TEST_ERRNO(getsockopt(sk, SOL_IP, IP_ADD_MEMBERSHIP, &value, &len),
+		   ENOPROTOOPT);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(sk));
+}
+END_SETUP()
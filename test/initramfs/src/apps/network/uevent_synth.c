@@ -0,0 +1,150 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#include <fcntl.h>
+#include <linux/filter.h>
+#include <netlink/netlink.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+#include "../common/test.h"
+
+#define UEVENT_PATH "/sys/class/mem/null/uevent"
+#define DEVPATH "/devices/virtual/mem/null"
+#define UUID "12345678-1234-1234-1234-123456789012"
+
+static int sk_uevent;
+static char buf[4096];
+
+FN_SETUP(uevent_socket)
+{
+	struct sockaddr_nl saddr = { .nl_family = AF_NETLINK,
+				     .nl_pid = 2001,
+				     .nl_groups = 0x1 };
+
+	sk_uevent = CHECK(socket(PF_NETLINK, SOCK_DGRAM | SOCK_NONBLOCK,
+				 NETLINK_KOBJECT_UEVENT));
+	CHECK(bind(sk_uevent, (struct sockaddr *)&saddr, sizeof(saddr)));
+}
+END_SETUP()
+
+static int write_uevent(const char *content)
+{
+	int fd, ret;
+
+	fd = open(UEVENT_PATH, O_WRONLY);
+	if (fd < 0)
+		return -1;
+
+	ret = write(fd, content, strlen(content));
+	close(fd);
+
+	return ret;
+}
+
+// Returns whether the uevent in `buf` with length `len` contains `env`.
+static int has_env(ssize_t len, const char *env)
+{
+	ssize_t i;
+
+	for (i = 0; i < len; i += strlen(buf + i) + 1)
+		if (strcmp(buf + i, env) == 0)
+			return 1;
+
+	return 0;
+}
+
+FN_TEST(synthetic_uevent)
+{
+	ssize_t len;
+
+	TEST_SUCC(write_uevent("change\n"));
+	len = TEST_RES(recv(sk_uevent, buf, sizeof(buf), 0),
+		       strcmp(buf, "change@" DEVPATH) == 0);
+	TEST_RES(has_env(len, "ACTION=change"), _ret == 1);
+	TEST_RES(has_env(len, "DEVPATH=" DEVPATH), _ret == 1);
+	TEST_RES(has_env(len, "SUBSYSTEM=mem"), _ret == 1);
+	TEST_RES(has_env(len, "SYNTH_UUID=0"), _ret == 1);
+	TEST_RES(has_env(len, "MAJOR=1"), _ret == 1);
+	TEST_RES(has_env(len, "MINOR=3"), _ret == 1);
+	TEST_RES(has_env(len, "DEVNAME=null"), _ret == 1);
+	TEST_ERRNO(recv(sk_uevent, buf, sizeof(buf), 0), EAGAIN);
+
+	TEST_SUCC(write_uevent("add " UUID " KEY=value"));
+	len = TEST_RES(recv(sk_uevent, buf, sizeof(buf), 0),
+		       strcmp(buf, "add@" DEVPATH) == 0);
+	TEST_RES(has_env(len, "SYNTH_UUID=" UUID), _ret == 1);
+	TEST_RES(has_env(len, "SYNTH_ARG_KEY=value"), _ret == 1);
+}
+END_TEST()
+
+FN_TEST(invalid_synthetic_uevent)
+{
+	TEST_ERRNO(write_uevent("invalid"), EINVAL);
+	TEST_ERRNO(write_uevent("add not-a-uuid"), EINVAL);
+	TEST_ERRNO(write_uevent("add " UUID " KEY"), EINVAL);
+	TEST_ERRNO(recv(sk_uevent, buf, sizeof(buf), 0), EAGAIN);
+}
+END_TEST()
+
+FN_TEST(filtered_uevent)
+{
+	struct sockaddr_nl saddr = { .nl_family = AF_NETLINK,
+				     .nl_pid = 2002,
+				     .nl_groups = 0x1 };
+	// Accepts the uevents whose actions start with 'a'.
+	struct sock_filter insns[] = {
+		BPF_STMT(BPF_LD | BPF_B | BPF_ABS, 0),
+		BPF_JUMP(BPF_JMP | BPF_JEQ | BPF_K, 'a', 0, 1),
+		BPF_STMT(BPF_RET | BPF_K, 0xffff),
+		BPF_STMT(BPF_RET | BPF_K, 0),
+	};
+	struct sock_fprog fprog = { .len = sizeof(insns) / sizeof(insns[0]),
+				    .filter = insns };
+	int sk, one = 1, zero = 0;
+
+	sk = TEST_SUCC(socket(PF_NETLINK, SOCK_DGRAM | SOCK_NONBLOCK,
+			      NETLINK_KOBJECT_UEVENT));
+
+	// The filter can be attached before the socket is bound.
+	TEST_ERRNO(setsockopt(sk, SOL_SOCKET, SO_DETACH_FILTER, &zero,
+			      sizeof(zero)),
+		   ENOENT);
+	TEST_SUCC(setsockopt(sk, SOL_SOCKET, SO_ATTACH_FILTER, &fprog,
+			     sizeof(fprog)));
+	TEST_SUCC(bind(sk, (struct sockaddr *)&saddr, sizeof(saddr)));
+
+	TEST_SUCC(write_uevent("change\n"));
+	TEST_SUCC(write_uevent("add\n"));
+	TEST_RES(recv(sk, buf, sizeof(buf), 0),
+		 strcmp(buf, "add@" DEVPATH) == 0);
+	TEST_ERRNO(recv(sk, buf, sizeof(buf), 0), EAGAIN);
+
+	// The other sockets are not affected by the filter.
+	TEST_RES(recv(sk_uevent, buf, sizeof(buf), 0),
+		 strcmp(buf, "change@" DEVPATH) == 0);
+	TEST_RES(recv(sk_uevent, buf, sizeof(buf), 0),
+		 strcmp(buf, "add@" DEVPATH) == 0);
+
+	// The filter cannot be changed after it is locked.
+	TEST_SUCC(setsockopt(sk, SOL_SOCKET, SO_LOCK_FILTER, &one,
+			     sizeof(one)));
+	TEST_ERRNO(setsockopt(sk, SOL_SOCKET, SO_LOCK_FILTER, &zero,
+			      sizeof(zero)),
+		   EPERM);
+	TEST_ERRNO(setsockopt(sk, SOL_SOCKET, SO_DETACH_FILTER, &zero,
+			      sizeof(zero)),
+		   EPERM);
+	TEST_ERRNO(setsockopt(sk, SOL_SOCKET, SO_ATTACH_FILTER, &fprog,
+			      sizeof(fprog)),
+		   EPERM);
+
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(sk_uevent));
+}
+END_SETUP()
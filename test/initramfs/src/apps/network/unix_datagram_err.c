@@ -469,6 +469,124 @@ FN_TEST(zero_sends_may_fail)
 }
 END_TEST()
 
+union cred_control {
+	char buf[CMSG_SPACE(sizeof(struct ucred))];
+	struct cmsghdr align;
+};
+
+static ssize_t send_cred(int sk, pid_t pid, uid_t uid, gid_t gid)
+{
+	char buf[1] = { 'c' };
+	struct iovec iov = { .iov_base = buf, .iov_len = sizeof(buf) };
+	union cred_control control;
+	struct msghdr msg = {
+		.msg_iov = &iov,
+		.msg_iovlen = 1,
+		.msg_control = control.buf,
+		.msg_controllen = sizeof(control.buf),
+	};
+	struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+	struct ucred cred = { .pid = pid, .uid = uid, .gid = gid };
+
+	cmsg->cmsg_level = SOL_SOCKET;
+	cmsg->cmsg_type = SCM_CREDENTIALS;
+	cmsg->cmsg_len = CMSG_LEN(sizeof(cred));
+	memcpy(CMSG_DATA(cmsg), &cred, sizeof(cred));
+
+	return sendmsg(sk, &msg, 0);
+}
+
+static ssize_t recv_cred(int sk, struct ucred *cred)
+{
+	char buf[1];
+	struct iovec iov = { .iov_base = buf, .iov_len = sizeof(buf) };
+	union cred_control control;
+	struct msghdr msg = {
+		.msg_iov = &iov,
+		.msg_iovlen = 1,
+		.msg_control = control.buf,
+		.msg_controllen = sizeof(control.buf),
+	};
+	struct cmsghdr *cmsg;
+	ssize_t ret;
+
+	ret = recvmsg(sk, &msg, 0);
+	if (ret < 0)
+		return ret;
+
+	cmsg = CMSG_FIRSTHDR(&msg);
+	if (cmsg == NULL || cmsg->cmsg_type != SCM_CREDENTIALS)
+		memset(cred, 0, sizeof(*cred));
+	else
+		memcpy(cred, CMSG_DATA(cmsg), sizeof(*cred));
+
+	return ret;
+}
+
+FN_TEST(scm_credentials)
+{
+	int fildes[2];
+	int one = 1;
+	struct ucred cred;
+	pid_t pid = getpid();
+	pid_t child;
+	int status;
+
+	TEST_SUCC(socketpair(AF_UNIX, SOCK_DGRAM | SOCK_NONBLOCK, 0, fildes));
+	TEST_SUCC(setsockopt(fildes[0], SOL_SOCKET, SO_PASSCRED, &one,
+			     sizeof(one)));
+
+	// Without credentials, the real UID/GID are sent
+	TEST_RES(send(fildes[1], "c", 1, 0), _ret == 1);
+	TEST_RES(recv_cred(fildes[0], &cred),
+		 cred.pid == pid && cred.uid == getuid() &&
+			 cred.gid == getgid());
+
+	// Privileged processes can send any valid credentials
+	TEST_RES(send_cred(fildes[1], pid, 1234, 5678), _ret == 1);
+	TEST_RES(recv_cred(fildes[0], &cred),
+		 cred.pid == pid && cred.uid == 1234 && cred.gid == 5678);
+	TEST_RES(send_cred(fildes[1], getppid(), 0, 0), _ret == 1);
+	TEST_RES(recv_cred(fildes[0], &cred),
+		 cred.pid == getppid() && cred.uid == 0 && cred.gid == 0);
+
+	// But the credentials must be valid
+	TEST_ERRNO(send_cred(fildes[1], pid, -1, 0), EINVAL);
+	TEST_ERRNO(send_cred(fildes[1], pid, 0, -1), EINVAL);
+	TEST_ERRNO(send_cred(fildes[1], -1, 0, 0), ESRCH);
+
+	child = TEST_SUCC(fork());
+	if (child == 0) {
+		CHECK(setresgid(1000, 1001, 1002));
+		CHECK(setresuid(1000, 1001, 1002));
+
+		// Unprivileged processes can send their own credentials
+		CHECK_WITH(send_cred(fildes[1], getpid(), 1001, 1002),
+			   _ret == 1);
+
+		// But not others' credentials
+		CHECK_WITH(send_cred(fildes[1], getppid(), 1000, 1000),
+			   _ret < 0 && errno == EPERM);
+		CHECK_WITH(send_cred(fildes[1], getpid(), 0, 1000),
+			   _ret < 0 && errno == EPERM);
+		CHECK_WITH(send_cred(fildes[1], getpid(), 1000, 0),
+			   _ret < 0 && errno == EPERM);
+
+		exit(EXIT_SUCCESS);
+	}
+
+	TEST_RES(wait4(child, &status, 0, NULL),
+		 _ret == child && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+	TEST_RES(recv_cred(fildes[0], &cred),
+		 cred.pid == child && cred.uid == 1001 && cred.gid == 1002);
+	TEST_ERRNO(recv(fildes[0], &one, sizeof(one), 0), EAGAIN);
+
+	TEST_SUCC(close(fildes[0]));
+	TEST_SUCC(close(fildes[1]));
+}
+END_TEST()
+
 FN_SETUP(cleanup)
 {
 	CHECK(close(sk_unbound));
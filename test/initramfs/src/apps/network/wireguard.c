@@ -0,0 +1,197 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#include <arpa/inet.h>
+#include <linux/wireguard.h>
+#include <net/if.h>
+#include <netlink/genl/ctrl.h>
+#include <netlink/genl/genl.h>
+#include <netlink/route/link.h>
+
+#include "../common/test.h"
+
+#define WG_NAME "wg0"
+#define WG_PORT 51820
+
+// The private key and the public key of Alice in RFC 7748
+static const uint8_t private_key[WG_KEY_LEN] = {
+	0x77, 0x07, 0x6d, 0x0a, 0x73, 0x18, 0xa5, 0x7d, 0x3c, 0x16, 0xc1,
+	0x72, 0x51, 0xb2, 0x66, 0x45, 0xdf, 0x4c, 0x2f, 0x87, 0xeb, 0xc0,
+	0x99, 0x2a, 0xb1, 0x77, 0xfb, 0xa5, 0x1d, 0xb9, 0x2c, 0x2a,
+};
+static const uint8_t public_key[WG_KEY_LEN] = {
+	0x85, 0x20, 0xf0, 0x09, 0x89, 0x30, 0xa7, 0x54, 0x74, 0x8b, 0x7d,
+	0xdc, 0xb4, 0x3e, 0xf7, 0x5a, 0x0d, 0xbf, 0x3a, 0x0d, 0x26, 0x38,
+	0x1a, 0xf4, 0xeb, 0xa4, 0xa9, 0x8e, 0xaa, 0x9b, 0x4e, 0x6a,
+};
+// The public key of Bob in RFC 7748
+static const uint8_t peer_key[WG_KEY_LEN] = {
+	0xde, 0x9e, 0xdb, 0x7d, 0x7b, 0x7d, 0xc1, 0xb4, 0xd3, 0x5b, 0x61,
+	0xc2, 0xec, 0xe4, 0x35, 0x37, 0x3f, 0x83, 0x43, 0xc8, 0x5b, 0x78,
+	0x67, 0x4d, 0xad, 0xfc, 0x7e, 0x14, 0x6f, 0x88, 0x2b, 0x4f,
+};
+
+static struct nl_sock *rtnl_sock;
+static struct nl_sock *genl_sock;
+static int wg_family;
+
+FN_SETUP(init)
+{
+	struct rtnl_link *link;
+
+	rtnl_sock = nl_socket_alloc();
+	CHECK(nl_connect(rtnl_sock, NETLINK_ROUTE));
+	genl_sock = nl_socket_alloc();
+	CHECK(genl_connect(genl_sock));
+
+	link = rtnl_link_alloc();
+	rtnl_link_set_name(link, WG_NAME);
+	CHECK(rtnl_link_set_type(link, "wireguard"));
+	CHECK(rtnl_link_add(rtnl_sock, link, NLM_F_CREATE | NLM_F_EXCL));
+	rtnl_link_put(link);
+}
+END_SETUP()
+
+FN_TEST(link_type)
+{
+	struct rtnl_link *link;
+
+	TEST_RES(rtnl_link_get_kernel(rtnl_sock, 0, WG_NAME, &link),
+		 _ret == 0 &&
+			 strcmp(rtnl_link_get_type(link), "wireguard") == 0);
+	rtnl_link_put(link);
+}
+END_TEST()
+
+FN_TEST(resolve_family)
+{
+	wg_family = TEST_RES(genl_ctrl_resolve(genl_sock, WG_GENL_NAME),
+			     _ret > 0);
+	TEST_RES(genl_ctrl_resolve(genl_sock, "nlctrl"), _ret == GENL_ID_CTRL);
+	TEST_RES(genl_ctrl_resolve(genl_sock, "none"), _ret < 0);
+}
+END_TEST()
+
+static struct nl_msg *new_request(int flags, uint8_t cmd, const char *name)
+{
+	struct nl_msg *msg;
+
+	msg = nlmsg_alloc();
+	genlmsg_put(msg, NL_AUTO_PORT, NL_AUTO_SEQ, wg_family, 0, flags, cmd,
+		    WG_GENL_VERSION);
+	nla_put_string(msg, WGDEVICE_A_IFNAME, name);
+
+	return msg;
+}
+
+FN_TEST(set_device)
+{
+	struct nl_msg *msg;
+	struct nlattr *peers, *peer, *ips, *ip;
+
+	msg = new_request(0, WG_CMD_SET_DEVICE, WG_NAME);
+	nla_put(msg, WGDEVICE_A_PRIVATE_KEY, WG_KEY_LEN, private_key);
+	nla_put_u16(msg, WGDEVICE_A_LISTEN_PORT, WG_PORT);
+	peers = nla_nest_start(msg, WGDEVICE_A_PEERS);
+	peer = nla_nest_start(msg, 0);
+	nla_put(msg, WGPEER_A_PUBLIC_KEY, WG_KEY_LEN, peer_key);
+	ips = nla_nest_start(msg, WGPEER_A_ALLOWEDIPS);
+	ip = nla_nest_start(msg, 0);
+	nla_put_u16(msg, WGALLOWEDIP_A_FAMILY, AF_INET);
+	nla_put_u32(msg, WGALLOWEDIP_A_IPADDR, inet_addr("10.0.0.2"));
+	nla_put_u8(msg, WGALLOWEDIP_A_CIDR_MASK, 32);
+	nla_nest_end(msg, ip);
+	nla_nest_end(msg, ips);
+	nla_nest_end(msg, peer);
+	nla_nest_end(msg, peers);
+	TEST_RES(nl_send_sync(genl_sock, msg), _ret == 0);
+
+	// Only WireGuard ifaces can be configured
+	msg = new_request(0, WG_CMD_SET_DEVICE, "lo");
+	TEST_RES(nl_send_sync(genl_sock, msg), _ret == -NLE_OPNOTSUPP);
+
+	msg = new_request(0, WG_CMD_SET_DEVICE, "none");
+	TEST_RES(nl_send_sync(genl_sock, msg), _ret == -NLE_OBJ_NOTFOUND);
+}
+END_TEST()
+
+struct device_info {
+	int found;
+	int has_public_key;
+	uint16_t listen_port;
+	int num_peers;
+	int has_peer_key;
+};
+
+static int parse_device(struct nl_msg *msg, void *arg)
+{
+	struct device_info *info = arg;
+	struct nlattr *attrs[WGDEVICE_A_MAX + 1];
+	struct nlattr *peer_attrs[WGPEER_A_MAX + 1];
+	struct nlattr *peer;
+	int rem;
+
+	if (genlmsg_parse(nlmsg_hdr(msg), 0, attrs, WGDEVICE_A_MAX, NULL) < 0)
+		return NL_SKIP;
+
+	info->found = 1;
+	if (attrs[WGDEVICE_A_PUBLIC_KEY] &&
+	    nla_len(attrs[WGDEVICE_A_PUBLIC_KEY]) == WG_KEY_LEN)
+		info->has_public_key =
+			memcmp(nla_data(attrs[WGDEVICE_A_PUBLIC_KEY]),
+			       public_key, WG_KEY_LEN) == 0;
+	if (attrs[WGDEVICE_A_LISTEN_PORT])
+		info->listen_port = nla_get_u16(attrs[WGDEVICE_A_LISTEN_PORT]);
+	if (!attrs[WGDEVICE_A_PEERS])
+		return NL_OK;
+
+	nla_for_each_nested(peer, attrs[WGDEVICE_A_PEERS], rem) {
+		info->num_peers++;
+		if (nla_parse_nested(peer_attrs, WGPEER_A_MAX, peer, NULL) < 0)
+			continue;
+		if (peer_attrs[WGPEER_A_PUBLIC_KEY] &&
+		    memcmp(nla_data(peer_attrs[WGPEER_A_PUBLIC_KEY]), peer_key,
+			   WG_KEY_LEN) == 0)
+			info->has_peer_key = 1;
+	}
+
+	return NL_OK;
+}
+
+FN_TEST(get_device)
+{
+	struct nl_msg *msg;
+	struct device_info info = {};
+
+	nl_socket_modify_cb(genl_sock, NL_CB_VALID, NL_CB_CUSTOM, parse_device,
+			    &info);
+
+	msg = new_request(NLM_F_DUMP, WG_CMD_GET_DEVICE, WG_NAME);
+	TEST_RES(nl_send_auto(genl_sock, msg), _ret > 0);
+	nlmsg_free(msg);
+	TEST_RES(nl_recvmsgs_default(genl_sock),
+		 _ret == 0 && info.found && info.has_public_key &&
+			 info.listen_port == WG_PORT && info.num_peers == 1 &&
+			 info.has_peer_key);
+
+	// The device can only be dumped
+	msg = new_request(0, WG_CMD_GET_DEVICE, WG_NAME);
+	TEST_RES(nl_send_sync(genl_sock, msg), _ret == -NLE_OPNOTSUPP);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	struct rtnl_link *link;
+
+	link = rtnl_link_alloc();
+	rtnl_link_set_name(link, WG_NAME);
+	CHECK(rtnl_link_delete(rtnl_sock, link));
+	rtnl_link_put(link);
+	CHECK_WITH(if_nametoindex(WG_NAME), _ret == 0 && errno == ENODEV);
+
+	nl_close(genl_sock);
+	nl_socket_free(genl_sock);
+	nl_close(rtnl_sock);
+	nl_socket_free(rtnl_sock);
+}
+END_SETUP()
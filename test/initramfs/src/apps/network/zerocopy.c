@@ -0,0 +1,192 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <unistd.h>
+#include <poll.h>
+#include <sys/socket.h>
+#include <netinet/in.h>
+#include <arpa/inet.h>
+#include <linux/errqueue.h>
+
+#include "../common/test.h"
+
+static struct sockaddr_in addr;
+static socklen_t addrlen = sizeof(addr);
+
+static struct sock_extended_err *serr;
+
+FN_SETUP(init)
+{
+	addr.sin_family = AF_INET;
+	CHECK(inet_aton("127.0.0.1", &addr.sin_addr));
+}
+END_SETUP()
+
+static int enable_zerocopy(int sk)
+{
+	int option = 1;
+
+	return setsockopt(sk, SOL_SOCKET, SO_ZEROCOPY, &option,
+			  sizeof(option));
+}
+
+static int recv_err(int sk, int level, int type)
+{
+	static char control[CMSG_SPACE(sizeof(struct sock_extended_err) +
+				       sizeof(struct sockaddr_in6))];
+	struct msghdr msg = { .msg_control = control,
+			      .msg_controllen = sizeof(control) };
+	struct cmsghdr *cmsg;
+	int ret;
+
+	serr = NULL;
+
+	ret = recvmsg(sk, &msg, MSG_ERRQUEUE);
+	if (ret < 0)
+		return ret;
+
+	cmsg = CMSG_FIRSTHDR(&msg);
+	if (cmsg != NULL && cmsg->cmsg_level == level &&
+	    cmsg->cmsg_type == type)
+		serr = (struct sock_extended_err *)CMSG_DATA(cmsg);
+
+	return ret;
+}
+
+static int check_completion(__u32 first_id, __u32 last_id)
+{
+	return serr != NULL && serr->ee_errno == 0 &&
+	       serr->ee_origin == SO_EE_ORIGIN_ZEROCOPY &&
+	       serr->ee_code == SO_EE_CODE_ZEROCOPY_COPIED &&
+	       serr->ee_info == first_id && serr->ee_data == last_id;
+}
+
+static short poll_err(int sk, int timeout)
+{
+	// `POLLERR` is always reported, even if it is not requested.
+	struct pollfd pfd = { .fd = sk, .events = 0 };
+
+	if (poll(&pfd, 1, timeout) < 0)
+		return -1;
+
+	return pfd.revents;
+}
+
+FN_TEST(zerocopy_option)
+{
+	int sk_tcp = TEST_SUCC(socket(AF_INET, SOCK_STREAM, 0));
+	int sk_udp = TEST_SUCC(socket(AF_INET, SOCK_DGRAM, 0));
+	int sk_unix = TEST_SUCC(socket(AF_UNIX, SOCK_STREAM, 0));
+	int option;
+	socklen_t option_len = sizeof(option);
+
+	TEST_RES(getsockopt(sk_tcp, SOL_SOCKET, SO_ZEROCOPY, &option,
+			    &option_len),
+		 option == 0);
+
+	TEST_SUCC(enable_zerocopy(sk_tcp));
+	TEST_SUCC(enable_zerocopy(sk_udp));
+	TEST_ERRNO(enable_zerocopy(sk_unix), EOPNOTSUPP);
+
+	TEST_RES(getsockopt(sk_tcp, SOL_SOCKET, SO_ZEROCOPY, &option,
+			    &option_len),
+		 option == 1 && option_len == sizeof(option));
+	TEST_RES(getsockopt(sk_udp, SOL_SOCKET, SO_ZEROCOPY, &option,
+			    &option_len),
+		 option == 1 && option_len == sizeof(option));
+
+	TEST_SUCC(close(sk_tcp));
+	TEST_SUCC(close(sk_udp));
+	TEST_SUCC(close(sk_unix));
+}
+END_TEST()
+
+FN_TEST(udp_zerocopy)
+{
+	int sk_send = TEST_SUCC(socket(AF_INET, SOCK_DGRAM, 0));
+	int sk_recv = TEST_SUCC(socket(AF_INET, SOCK_DGRAM, 0));
+
+	addr.sin_port = 0;
+	TEST_SUCC(bind(sk_recv, (struct sockaddr *)&addr, addrlen));
+	TEST_SUCC(getsockname(sk_recv, (struct sockaddr *)&addr, &addrlen));
+	TEST_SUCC(connect(sk_send, (struct sockaddr *)&addr, addrlen));
+
+	// `MSG_ZEROCOPY` is ignored without `SO_ZEROCOPY`.
+	TEST_RES(send(sk_send, "abc", 3, MSG_ZEROCOPY), _ret == 3);
+	TEST_ERRNO(recv_err(sk_send, SOL_IP, IP_RECVERR), EAGAIN);
+
+	TEST_SUCC(enable_zerocopy(sk_send));
+	TEST_ERRNO(recv_err(sk_send, SOL_IP, IP_RECVERR), EAGAIN);
+
+	TEST_RES(send(sk_send, "abc", 3, MSG_ZEROCOPY), _ret == 3);
+	TEST_RES(send(sk_send, "abc", 3, 0), _ret == 3);
+	TEST_RES(send(sk_send, "abc", 3, MSG_ZEROCOPY), _ret == 3);
+	TEST_RES(send(sk_send, "abc", 3, MSG_ZEROCOPY), _ret == 3);
+
+	// The notifications of consecutive sends are merged.
+	TEST_RES(poll_err(sk_send, 1000), _ret == POLLERR);
+	TEST_RES(recv_err(sk_send, SOL_IP, IP_RECVERR),
+		 _ret == 0 && check_completion(0, 2));
+	TEST_ERRNO(recv_err(sk_send, SOL_IP, IP_RECVERR), EAGAIN);
+	TEST_RES(poll_err(sk_send, 0), _ret == 0);
+
+	TEST_SUCC(close(sk_send));
+	TEST_SUCC(close(sk_recv));
+}
+END_TEST()
+
+FN_TEST(udp6_zerocopy)
+{
+	struct sockaddr_in6 addr6 = { .sin6_family = AF_INET6,
+				      .sin6_addr = in6addr_loopback };
+	socklen_t addr6len = sizeof(addr6);
+	int sk_send = TEST_SUCC(socket(AF_INET6, SOCK_DGRAM, 0));
+	int sk_recv = TEST_SUCC(socket(AF_INET6, SOCK_DGRAM, 0));
+
+	TEST_SUCC(bind(sk_recv, (struct sockaddr *)&addr6, addr6len));
+	TEST_SUCC(getsockname(sk_recv, (struct sockaddr *)&addr6, &addr6len));
+	TEST_SUCC(connect(sk_send, (struct sockaddr *)&addr6, addr6len));
+
+	TEST_SUCC(enable_zerocopy(sk_send));
+	TEST_RES(send(sk_send, "abc", 3, MSG_ZEROCOPY), _ret == 3);
+
+	TEST_RES(poll_err(sk_send, 1000), _ret == POLLERR);
+	TEST_RES(recv_err(sk_send, SOL_IPV6, IPV6_RECVERR),
+		 _ret == 0 && check_completion(0, 0));
+
+	TEST_SUCC(close(sk_send));
+	TEST_SUCC(close(sk_recv));
+}
+END_TEST()
+
+FN_TEST(tcp_zerocopy)
+{
+	int sk_listen = TEST_SUCC(socket(AF_INET, SOCK_STREAM, 0));
+	int sk_connect = TEST_SUCC(socket(AF_INET, SOCK_STREAM, 0));
+	int sk_accept;
+
+	addr.sin_port = 0;
+	TEST_SUCC(bind(sk_listen, (struct sockaddr *)&addr, addrlen));
+	TEST_SUCC(getsockname(sk_listen, (struct sockaddr *)&addr, &addrlen));
+	TEST_SUCC(listen(sk_listen, 1));
+	TEST_SUCC(connect(sk_connect, (struct sockaddr *)&addr, addrlen));
+	sk_accept = TEST_SUCC(accept(sk_listen, NULL, NULL));
+
+	TEST_SUCC(enable_zerocopy(sk_connect));
+	TEST_ERRNO(recv_err(sk_connect, SOL_IP, IP_RECVERR), EAGAIN);
+
+	TEST_RES(send(sk_connect, "abc", 3, MSG_ZEROCOPY), _ret == 3);
+	TEST_RES(send(sk_connect, "def", 3, MSG_ZEROCOPY), _ret == 3);
+
+	TEST_RES(poll_err(sk_connect, 1000), _ret == POLLERR);
+	TEST_RES(recv_err(sk_connect, SOL_IP, IP_RECVERR),
+		 _ret == 0 && check_completion(0, 1));
+	TEST_ERRNO(recv_err(sk_connect, SOL_IP, IP_RECVERR), EAGAIN);
+	TEST_RES(poll_err(sk_connect, 0), _ret == 0);
+
+	TEST_SUCC(close(sk_listen));
+	TEST_SUCC(close(sk_connect));
+	TEST_SUCC(close(sk_accept));
+}
+END_TEST()
@@ -440,6 +440,48 @@ else
     echo -e "Verified: pids.peak retained"
 fi
 
+# -- 4.4 io -------------------------------------------------------------------
+
+log_section "Section 4.4: io"
+
+log_step "4.4.1 Check io.stat exists in root"
+verify "io.stat exists in root" \
+    "ls $CGROUP_ROOT/io.stat" \
+    "$CGROUP_ROOT/io.stat"
+
+log_step "4.4.2 Enable io in root"
+echo "+io" > "$CGROUP_ROOT/cgroup.subtree_control"
+verify "io.weight defaults to 100" \
+    "cat $CGROUP_ROOT/$CGROUP_NAME/io.weight" \
+    "default 100"
+verify "io.max is empty by default" \
+    "cat $CGROUP_ROOT/$CGROUP_NAME/io.max" \
+    ""
+
+log_step "4.4.3 Set io.weight"
+echo "200" > "$CGROUP_ROOT/$CGROUP_NAME/io.weight"
+verify "io.weight set to 200" \
+    "cat $CGROUP_ROOT/$CGROUP_NAME/io.weight" \
+    "default 200"
+echo "default 100" > "$CGROUP_ROOT/$CGROUP_NAME/io.weight"
+verify "io.weight reset to 100" \
+    "cat $CGROUP_ROOT/$CGROUP_NAME/io.weight" \
+    "default 100"
+
+log_step "4.4.4 Reject invalid io.weight"
+if echo "0" > "$CGROUP_ROOT/$CGROUP_NAME/io.weight" 2>/dev/null; then
+    echo "Error: io.weight accepted an invalid weight!"
+    exit 1
+else
+    echo "Verified: io.weight rejected an invalid weight"
+fi
+
+log_step "4.4.5 Disable io in root"
+echo "-io" > "$CGROUP_ROOT/cgroup.subtree_control"
+verify "io.weight removed after disabling" \
+    "ls $CGROUP_ROOT/$CGROUP_NAME/io.weight" \
+    "ls: $CGROUP_ROOT/$CGROUP_NAME/io.weight: No such file or directory"
+
 # --- Section 5: Teardown ------------------------------------------------------
 
 log_section "Section 5: Teardown"
@@ -0,0 +1,179 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/sysmacros.h>
+#include <unistd.h>
+
+#include "../../common/test.h"
+
+#define CGROUP_ROOT "/sys/fs/cgroup"
+#define CGROUP_PATH CGROUP_ROOT "/io_test"
+#define IO_MAX CGROUP_PATH "/io.max"
+#define IO_WEIGHT CGROUP_PATH "/io.weight"
+#define IO_STAT CGROUP_PATH "/io.stat"
+#define DISK_PATH "/dev/vda"
+
+static char buf[4096];
+static char disk[32];
+static char expected[256];
+
+static ssize_t read_file(const char *path)
+{
+	int fd;
+	ssize_t len;
+
+	fd = open(path, O_RDONLY);
+	if (fd < 0)
+		return -1;
+
+	len = read(fd, buf, sizeof(buf) - 1);
+	close(fd);
+	if (len >= 0)
+		buf[len] = '\0';
+
+	return len;
+}
+
+static ssize_t write_file(const char *path, const char *value)
+{
+	int fd;
+	ssize_t len;
+
+	fd = open(path, O_WRONLY);
+	if (fd < 0)
+		return -1;
+
+	len = write(fd, value, strlen(value));
+	close(fd);
+
+	return len;
+}
+
+static int write_line(const char *path, const char *format, const char *arg)
+{
+	char line[128];
+
+	snprintf(line, sizeof(line), format, arg);
+	return write_file(path, line) < 0 ? -1 : 0;
+}
+
+FN_SETUP(cgroup)
+{
+	struct stat st;
+
+	CHECK(stat(DISK_PATH, &st));
+	snprintf(disk, sizeof(disk), "%u:%u", major(st.st_rdev),
+		 minor(st.st_rdev));
+
+	CHECK(mkdir(CGROUP_PATH, 0755));
+	CHECK(write_file(CGROUP_ROOT "/cgroup.subtree_control", "+io"));
+}
+END_SETUP()
+
+FN_TEST(root_files)
+{
+	TEST_SUCC(access(CGROUP_ROOT "/io.stat", F_OK));
+	TEST_ERRNO(access(CGROUP_ROOT "/io.max", F_OK), ENOENT);
+	TEST_ERRNO(access(CGROUP_ROOT "/io.weight", F_OK), ENOENT);
+}
+END_TEST()
+
+FN_TEST(weight)
+{
+	TEST_RES(read_file(IO_WEIGHT), strcmp(buf, "default 100\n") == 0);
+
+	TEST_SUCC(write_file(IO_WEIGHT, "200"));
+	TEST_RES(read_file(IO_WEIGHT), strcmp(buf, "default 200\n") == 0);
+	TEST_SUCC(write_file(IO_WEIGHT, "default 10000"));
+	TEST_RES(read_file(IO_WEIGHT), strcmp(buf, "default 10000\n") == 0);
+
+	TEST_SUCC(write_line(IO_WEIGHT, "%s 300", disk));
+	snprintf(expected, sizeof(expected), "default 10000\n%s 300\n", disk);
+	TEST_RES(read_file(IO_WEIGHT), strcmp(buf, expected) == 0);
+	TEST_SUCC(write_line(IO_WEIGHT, "%s default", disk));
+	TEST_RES(read_file(IO_WEIGHT), strcmp(buf, "default 10000\n") == 0);
+
+	TEST_ERRNO(write_file(IO_WEIGHT, "0"), EINVAL);
+	TEST_ERRNO(write_file(IO_WEIGHT, "10001"), EINVAL);
+	TEST_ERRNO(write_file(IO_WEIGHT, "abc"), EINVAL);
+	TEST_ERRNO(write_file(IO_WEIGHT, "default"), EINVAL);
+	TEST_ERRNO(write_file(IO_WEIGHT, "default 1 2"), EINVAL);
+	TEST_ERRNO(write_line(IO_WEIGHT, "%s 0", disk), EINVAL);
+	TEST_ERRNO(write_file(IO_WEIGHT, "8:x 100"), EINVAL);
+	TEST_ERRNO(write_file(IO_WEIGHT, "4095:1048575 100"), ENOENT);
+	TEST_RES(read_file(IO_WEIGHT), strcmp(buf, "default 10000\n") == 0);
+
+	TEST_SUCC(write_file(IO_WEIGHT, "default 100"));
+	TEST_RES(read_file(IO_WEIGHT), strcmp(buf, "default 100\n") == 0);
+}
+END_TEST()
+
+FN_TEST(max)
+{
+	TEST_RES(read_file(IO_MAX), _ret == 0);
+
+	TEST_SUCC(write_line(IO_MAX, "%s rbps=1048576 wiops=100", disk));
+	snprintf(expected, sizeof(expected),
+		 "%s rbps=1048576 wbps=max riops=max wiops=100\n", disk);
+	TEST_RES(read_file(IO_MAX), strcmp(buf, expected) == 0);
+
+	// The limits that are not specified are kept.
+	TEST_SUCC(write_line(IO_MAX, "%s wbps=4096", disk));
+	snprintf(expected, sizeof(expected),
+		 "%s rbps=1048576 wbps=4096 riops=max wiops=100\n", disk);
+	TEST_RES(read_file(IO_MAX), strcmp(buf, expected) == 0);
+
+	TEST_ERRNO(write_line(IO_MAX, "%s rbps=0", disk), EINVAL);
+	TEST_ERRNO(write_line(IO_MAX, "%s rbps=-1", disk), EINVAL);
+	TEST_ERRNO(write_line(IO_MAX, "%s rbps", disk), EINVAL);
+	TEST_ERRNO(write_line(IO_MAX, "%s foo=1", disk), EINVAL);
+	TEST_ERRNO(write_file(IO_MAX, "rbps=1"), EINVAL);
+	TEST_ERRNO(write_file(IO_MAX, "4095:1048575 rbps=1"), ENOENT);
+	TEST_RES(read_file(IO_MAX), strcmp(buf, expected) == 0);
+
+	// A disk without any limits is not shown.
+	TEST_SUCC(write_line(IO_MAX, "%s rbps=max wbps=max wiops=max", disk));
+	TEST_RES(read_file(IO_MAX), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(stat)
+{
+	char pid[16];
+	char line[64];
+	unsigned long long rbytes = 0, rios = 0;
+	char *entry = NULL;
+	int fd;
+
+	TEST_RES(read_file(IO_STAT), _ret == 0);
+
+	// The reads from the block device bypass the page cache, so they are
+	// accounted to the cgroup at once.
+	snprintf(pid, sizeof(pid), "%d", getpid());
+	TEST_SUCC(write_file(CGROUP_PATH "/cgroup.procs", pid));
+	fd = TEST_SUCC(open(DISK_PATH, O_RDONLY));
+	TEST_RES(pread(fd, buf, sizeof(buf), 0), _ret == sizeof(buf));
+	TEST_SUCC(close(fd));
+	TEST_SUCC(write_file(CGROUP_ROOT "/cgroup.procs", pid));
+
+	snprintf(line, sizeof(line), "%s rbytes=", disk);
+	TEST_RES(read_file(IO_STAT), (entry = strstr(buf, line)) != NULL);
+	if (entry != NULL)
+		sscanf(entry + strlen(disk),
+		       " rbytes=%llu wbytes=%*u rios=%llu", &rbytes, &rios);
+	TEST_RES(rbytes, _ret >= sizeof(buf));
+	TEST_RES(rios, _ret >= 1);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(write_file(CGROUP_ROOT "/cgroup.subtree_control", "-io"));
+	CHECK(rmdir(CGROUP_PATH));
+}
+END_SETUP()
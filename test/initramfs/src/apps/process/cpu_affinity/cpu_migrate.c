@@ -0,0 +1,140 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../../common/test.h"
+
+#include <pthread.h>
+#include <sched.h>
+#include <stdatomic.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+static cpu_set_t old_set;
+static int first_cpu = -1;
+static int second_cpu = -1;
+
+static pid_t spinner_tid;
+static atomic_int spinner_cpu = -1;
+static atomic_int spinner_stop;
+
+static void *spin(void *arg)
+{
+	(void)arg;
+
+	spinner_tid = syscall(SYS_gettid);
+	while (!atomic_load(&spinner_stop))
+		atomic_store(&spinner_cpu, sched_getcpu());
+
+	return NULL;
+}
+
+static void set_single_cpu(cpu_set_t *set, int cpu)
+{
+	CPU_ZERO(set);
+	CPU_SET(cpu, set);
+}
+
+FN_SETUP(cpus)
+{
+	int i;
+
+	CHECK(sched_getaffinity(0, sizeof(old_set), &old_set));
+
+	for (i = 0; i < CPU_SETSIZE; i++) {
+		if (!CPU_ISSET(i, &old_set))
+			continue;
+		if (first_cpu < 0)
+			first_cpu = i;
+		else if (second_cpu < 0)
+			second_cpu = i;
+	}
+}
+END_SETUP()
+
+FN_TEST(migrate_current)
+{
+	cpu_set_t set;
+	int i;
+
+	// The current thread must be running on the only allowed CPU once
+	// `sched_setaffinity` returns.
+	for (i = 0; i < CPU_SETSIZE; i++) {
+		if (!CPU_ISSET(i, &old_set))
+			continue;
+
+		set_single_cpu(&set, i);
+		TEST_SUCC(sched_setaffinity(0, sizeof(set), &set));
+		TEST_RES(sched_getcpu(), _ret == i);
+	}
+
+	TEST_SUCC(sched_setaffinity(0, sizeof(old_set), &old_set));
+}
+END_TEST()
+
+FN_TEST(migrate_current_from_busy_cpu)
+{
+	cpu_set_t set;
+	pthread_attr_t attr;
+	pthread_t spinner;
+
+	if (second_cpu < 0)
+		return;
+
+	// Pin a busy thread to the first CPU, so the current thread is not the
+	// only runnable thread there when it is moved away.
+	set_single_cpu(&set, first_cpu);
+	TEST_SUCC(sched_setaffinity(0, sizeof(set), &set));
+	TEST_RES(pthread_attr_init(&attr), _ret == 0);
+	TEST_RES(pthread_attr_setaffinity_np(&attr, sizeof(set), &set),
+		 _ret == 0);
+	atomic_store(&spinner_stop, 0);
+	TEST_RES(pthread_create(&spinner, &attr, spin, NULL), _ret == 0);
+	TEST_RES(pthread_attr_destroy(&attr), _ret == 0);
+	while (atomic_load(&spinner_cpu) < 0)
+		sched_yield();
+
+	set_single_cpu(&set, second_cpu);
+	TEST_SUCC(sched_setaffinity(0, sizeof(set), &set));
+	TEST_RES(sched_getcpu(), _ret == second_cpu);
+
+	set_single_cpu(&set, first_cpu);
+	TEST_SUCC(sched_setaffinity(0, sizeof(set), &set));
+	TEST_RES(sched_getcpu(), _ret == first_cpu);
+
+	atomic_store(&spinner_stop, 1);
+	TEST_RES(pthread_join(spinner, NULL), _ret == 0);
+	TEST_SUCC(sched_setaffinity(0, sizeof(old_set), &old_set));
+}
+END_TEST()
+
+FN_TEST(migrate_other_thread)
+{
+	cpu_set_t set;
+	pthread_t spinner;
+
+	if (second_cpu < 0)
+		return;
+
+	// A thread running on a CPU that becomes disallowed is moved away by
+	// the scheduler, even if the thread never blocks or yields.
+	set_single_cpu(&set, first_cpu);
+	TEST_SUCC(sched_setaffinity(0, sizeof(set), &set));
+	atomic_store(&spinner_cpu, -1);
+	atomic_store(&spinner_stop, 0);
+	TEST_RES(pthread_create(&spinner, NULL, spin, NULL), _ret == 0);
+	while (atomic_load(&spinner_cpu) != first_cpu)
+		sched_yield();
+
+	set_single_cpu(&set, second_cpu);
+	TEST_SUCC(sched_setaffinity(spinner_tid, sizeof(set), &set));
+	while (atomic_load(&spinner_cpu) != second_cpu)
+		usleep(1000);
+	TEST_RES(sched_getaffinity(spinner_tid, sizeof(set), &set),
+		 CPU_COUNT(&set) == 1 && CPU_ISSET(second_cpu, &set));
+
+	atomic_store(&spinner_stop, 1);
+	TEST_RES(pthread_join(spinner, NULL), _ret == 0);
+	TEST_SUCC(sched_setaffinity(0, sizeof(old_set), &old_set));
+}
+END_TEST()
@@ -0,0 +1,230 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <fcntl.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "../../common/test.h"
+
+#define BINFMT_DIR "/proc/sys/fs/binfmt_misc"
+
+#define SCRIPT_PATH "/tmp/execve_interp.sh"
+#define EXT_PATH "/tmp/execve_interp.bftest"
+#define MAGIC_PATH "/tmp/execve_interp_magic"
+
+static char output[4096];
+
+static void write_file(const char *path, const char *content, mode_t mode)
+{
+	int fd;
+
+	fd = CHECK(open(path, O_WRONLY | O_CREAT | O_TRUNC, mode));
+	CHECK_WITH(write(fd, content, strlen(content)),
+		   _ret == strlen(content));
+	CHECK(close(fd));
+}
+
+static int write_str(const char *path, const char *content)
+{
+	int fd;
+	ssize_t len;
+
+	fd = open(path, O_WRONLY);
+	if (fd < 0)
+		return -1;
+
+	len = write(fd, content, strlen(content));
+	close(fd);
+
+	return len;
+}
+
+static ssize_t read_str(const char *path)
+{
+	int fd;
+	ssize_t len;
+
+	fd = open(path, O_RDONLY);
+	if (fd < 0)
+		return -1;
+
+	len = read(fd, output, sizeof(output) - 1);
+	close(fd);
+	if (len >= 0)
+		output[len] = '\0';
+
+	return len;
+}
+
+// Runs the executable in a child process, and collects its standard output.
+static int run(const char *path, char *const argv[])
+{
+	int pipefd[2];
+	pid_t pid;
+	int status;
+	ssize_t len, total = 0;
+
+	CHECK(pipe(pipefd));
+
+	pid = CHECK(fork());
+	if (pid == 0) {
+		CHECK(dup2(pipefd[1], STDOUT_FILENO));
+		execv(path, argv);
+		exit(errno);
+	}
+
+	CHECK(close(pipefd[1]));
+	while ((len = CHECK(read(pipefd[0], output + total,
+				 sizeof(output) - 1 - total))) > 0)
+		total += len;
+	output[total] = '\0';
+	CHECK(close(pipefd[0]));
+
+	CHECK_WITH(waitpid(pid, &status, 0), _ret == pid);
+	if (!WIFEXITED(status))
+		return -1;
+	if (WEXITSTATUS(status) != 0) {
+		errno = WEXITSTATUS(status);
+		return -1;
+	}
+
+	return 0;
+}
+
+FN_TEST(shebang_single_arg)
+{
+	char *argv[] = { "script", "x", "y", NULL };
+
+	// Everything after the interpreter path is passed as a single argument.
+	write_file(SCRIPT_PATH, "#!/bin/echo  a  b  \n", 0755);
+	TEST_RES(run(SCRIPT_PATH, argv),
+		 strcmp(output, "a  b " SCRIPT_PATH " x y\n") == 0);
+
+	write_file(SCRIPT_PATH, "#!/bin/echo", 0755);
+	TEST_RES(run(SCRIPT_PATH, argv),
+		 strcmp(output, SCRIPT_PATH " x y\n") == 0);
+
+	TEST_SUCC(unlink(SCRIPT_PATH));
+}
+END_TEST()
+
+FN_TEST(shebang_too_long)
+{
+	char *argv[] = { "script", NULL };
+	char line[512];
+
+	// The argument is truncated.
+	memset(line, 'a', sizeof(line));
+	memcpy(line, "#!/bin/echo ", 12);
+	line[sizeof(line) - 2] = '\n';
+	line[sizeof(line) - 1] = '\0';
+	write_file(SCRIPT_PATH, line, 0755);
+	TEST_RES(run(SCRIPT_PATH, argv),
+		 strlen(output) == 256 - 12 + 1 + strlen(SCRIPT_PATH) + 1);
+
+	// The interpreter path is truncated.
+	memset(line, 'a', sizeof(line));
+	memcpy(line, "#!/", 3);
+	line[sizeof(line) - 2] = '\n';
+	line[sizeof(line) - 1] = '\0';
+	write_file(SCRIPT_PATH, line, 0755);
+	TEST_ERRNO(run(SCRIPT_PATH, argv), ENOEXEC);
+
+	TEST_SUCC(unlink(SCRIPT_PATH));
+}
+END_TEST()
+
+FN_TEST(binfmt_misc_extension)
+{
+	char *argv[] = { "argv0", "x", NULL };
+
+	write_file(EXT_PATH, "", 0755);
+
+	TEST_ERRNO(run(EXT_PATH, argv), ENOEXEC);
+
+	TEST_RES(write_str(BINFMT_DIR "/register",
+			   ":bftest:E::bftest::/bin/echo:"),
+		 _ret == 30);
+	TEST_RES(read_str(BINFMT_DIR "/bftest"),
+		 strcmp(output, "enabled\n"
+				"interpreter /bin/echo\n"
+				"flags: \n"
+				"extension .bftest\n") == 0);
+	TEST_ERRNO(write_str(BINFMT_DIR "/register",
+			     ":bftest:E::bftest::/bin/echo:"),
+		   EEXIST);
+
+	TEST_RES(run(EXT_PATH, argv), strcmp(output, EXT_PATH " x\n") == 0);
+
+	// Disable the entry.
+	TEST_RES(write_str(BINFMT_DIR "/bftest", "0"), _ret == 1);
+	TEST_RES(read_str(BINFMT_DIR "/bftest"),
+		 strncmp(output, "disabled\n", 9) == 0);
+	TEST_ERRNO(run(EXT_PATH, argv), ENOEXEC);
+	TEST_RES(write_str(BINFMT_DIR "/bftest", "1\n"), _ret == 2);
+
+	// Disable binfmt_misc as a whole.
+	TEST_RES(write_str(BINFMT_DIR "/status", "0"), _ret == 1);
+	TEST_RES(read_str(BINFMT_DIR "/status"),
+		 strcmp(output, "disabled\n") == 0);
+	TEST_ERRNO(run(EXT_PATH, argv), ENOEXEC);
+	TEST_RES(write_str(BINFMT_DIR "/status", "1"), _ret == 1);
+	TEST_RES(read_str(BINFMT_DIR "/status"),
+		 strcmp(output, "enabled\n") == 0);
+
+	// Remove the entry.
+	TEST_RES(write_str(BINFMT_DIR "/bftest", "-1"), _ret == 2);
+	TEST_ERRNO(read_str(BINFMT_DIR "/bftest"), ENOENT);
+	TEST_ERRNO(run(EXT_PATH, argv), ENOEXEC);
+
+	TEST_SUCC(unlink(EXT_PATH));
+}
+END_TEST()
+
+FN_TEST(binfmt_misc_magic)
+{
+	char *argv[] = { "argv0", "x", NULL };
+
+	write_file(MAGIC_PATH, "\x01XYZ", 0755);
+
+	TEST_SUCC(write_str(BINFMT_DIR "/register",
+			    ":bfmagic:M:1:XY\\x5a::/bin/echo:P"));
+	TEST_RES(read_str(BINFMT_DIR "/bfmagic"),
+		 strcmp(output, "enabled\n"
+				"interpreter /bin/echo\n"
+				"flags: P\n"
+				"offset 1\n"
+				"magic 58595a\n") == 0);
+
+	// The original `argv[0]` is preserved.
+	TEST_RES(run(MAGIC_PATH, argv),
+		 strcmp(output, MAGIC_PATH " argv0 x\n") == 0);
+
+	// Remove all entries.
+	TEST_SUCC(write_str(BINFMT_DIR "/status", "-1"));
+	TEST_ERRNO(read_str(BINFMT_DIR "/bfmagic"), ENOENT);
+	TEST_ERRNO(run(MAGIC_PATH, argv), ENOEXEC);
+
+	TEST_SUCC(unlink(MAGIC_PATH));
+}
+END_TEST()
+
+FN_TEST(binfmt_misc_invalid)
+{
+	TEST_ERRNO(write_str(BINFMT_DIR "/register", ":bad:X::XYZ::/bin/echo:"),
+		   EINVAL);
+	TEST_ERRNO(write_str(BINFMT_DIR "/register", ":bad:M::XYZ:::"),
+		   EINVAL);
+	TEST_ERRNO(write_str(BINFMT_DIR "/register", ":bad:M:256:XYZ::/bin/echo:"),
+		   EINVAL);
+	TEST_ERRNO(write_str(BINFMT_DIR "/register", ":bad:E:1:ext::/bin/echo:"),
+		   EINVAL);
+	TEST_ERRNO(write_str(BINFMT_DIR "/status", "2"), EINVAL);
+	TEST_ERRNO(read_str(BINFMT_DIR "/bad"), ENOENT);
+}
+END_TEST()
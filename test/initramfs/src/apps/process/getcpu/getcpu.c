@@ -1,7 +1,10 @@
 // SPDX-License-Identifier: MPL-2.0
 
+#define _GNU_SOURCE
+
 #include "../../common/test.h"
 
+#include <sched.h>
 #include <sys/syscall.h>
 #include <unistd.h>
 
@@ -27,3 +30,41 @@ FN_TEST(getcpu)
 			 node.second == 0xdeadbeef);
 }
 END_TEST()
+
+#ifdef __x86_64__
+// Reads the CPU data from the segment limit of the `CPUNODE` descriptor,
+// which is how the vDSO obtains the current CPU on x86-64.
+static unsigned int read_cpunode(void)
+{
+	unsigned int p = 0xdeadbeef;
+	unsigned int seg = 15 * 8 + 3;
+
+	asm volatile("lsl %[seg], %[p]" : [p] "+r"(p) : [seg] "r"(seg));
+	return p;
+}
+#endif
+
+FN_TEST(getcpu_in_user_space)
+{
+	cpu_set_t old_set, set;
+	int i;
+
+	TEST_SUCC(sched_getaffinity(0, sizeof(old_set), &old_set));
+
+	for (i = 0; i < CPU_SETSIZE; i++) {
+		if (!CPU_ISSET(i, &old_set))
+			continue;
+
+		CPU_ZERO(&set);
+		CPU_SET(i, &set);
+		TEST_SUCC(sched_setaffinity(0, sizeof(set), &set));
+
+		TEST_RES(sched_getcpu(), _ret == i);
+#ifdef __x86_64__
+		TEST_RES(read_cpunode(), _ret == i);
+#endif
+	}
+
+	TEST_SUCC(sched_setaffinity(0, sizeof(old_set), &old_set));
+}
+END_TEST()
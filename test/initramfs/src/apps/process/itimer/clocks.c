@@ -0,0 +1,214 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+#include <signal.h>
+#include <stdint.h>
+#include <time.h>
+#include <unistd.h>
+#include <sys/syscall.h>
+#include <sys/timerfd.h>
+#include <sys/timex.h>
+#include <sys/wait.h>
+#include <linux/capability.h>
+#include "../../common/test.h"
+
+static int64_t diff_ns(const struct timespec *a, const struct timespec *b)
+{
+	return (a->tv_sec - b->tv_sec) * 1000000000LL +
+	       (a->tv_nsec - b->tv_nsec);
+}
+
+static int old_tai;
+
+FN_SETUP(save_tai)
+{
+	struct timex tx = { .modes = 0 };
+
+	CHECK(adjtimex(&tx));
+	old_tai = tx.tai;
+}
+END_SETUP()
+
+FN_TEST(adjtimex_read)
+{
+	struct timex tx = { .modes = 0 };
+
+	TEST_RES(adjtimex(&tx), _ret == TIME_OK || _ret == TIME_ERROR);
+	TEST_RES(0, tx.modes == 0 && tx.precision >= 1 &&
+			    tx.tolerance == 500 << 16 && tx.tick == 10000);
+
+	tx.modes = ADJ_OFFSET_SS_READ;
+	TEST_RES(adjtimex(&tx), _ret >= 0 && tx.offset == 0);
+
+	tx.modes = 0;
+	TEST_SUCC(clock_adjtime(CLOCK_REALTIME, &tx));
+	TEST_ERRNO(clock_adjtime(CLOCK_MONOTONIC, &tx), EOPNOTSUPP);
+	TEST_ERRNO(clock_adjtime(10, &tx), EINVAL);
+
+	// `ADJ_ADJTIME` without `ADJ_OFFSET`
+	tx.modes = 0x8000;
+	TEST_ERRNO(adjtimex(&tx), EINVAL);
+}
+END_TEST()
+
+FN_TEST(adjtimex_status)
+{
+	struct timex tx = { .modes = 0 };
+
+	TEST_SUCC(adjtimex(&tx));
+	int old_status = tx.status;
+
+	// Read-only flags cannot be set.
+	tx.modes = ADJ_STATUS;
+	tx.status = STA_UNSYNC | STA_CLOCKERR;
+	TEST_RES(adjtimex(&tx),
+		 _ret == TIME_ERROR && (tx.status & STA_UNSYNC) &&
+			 !(tx.status & STA_CLOCKERR));
+
+	tx.modes = ADJ_NANO;
+	TEST_RES(adjtimex(&tx), tx.status & STA_NANO);
+	tx.modes = ADJ_MICRO;
+	TEST_RES(adjtimex(&tx), !(tx.status & STA_NANO));
+
+	tx.modes = ADJ_STATUS;
+	tx.status = old_status;
+	TEST_SUCC(adjtimex(&tx));
+}
+END_TEST()
+
+FN_TEST(clock_tai)
+{
+	struct timex tx = { .modes = ADJ_TAI, .constant = 37 };
+	struct timespec real, tai;
+
+	TEST_RES(adjtimex(&tx), tx.tai == 37);
+
+	TEST_SUCC(clock_gettime(CLOCK_REALTIME, &real));
+	TEST_SUCC(clock_gettime(CLOCK_TAI, &tai));
+	TEST_RES(0, diff_ns(&tai, &real) >= 37000000000LL &&
+			    diff_ns(&tai, &real) < 38000000000LL);
+
+	// Invalid offsets are silently ignored.
+	tx.constant = -1;
+	TEST_RES(adjtimex(&tx), tx.tai == 37);
+
+	tx.constant = old_tai;
+	TEST_RES(adjtimex(&tx), tx.tai == old_tai);
+}
+END_TEST()
+
+FN_TEST(clock_boottime)
+{
+	struct timespec mono, boot;
+
+	TEST_SUCC(clock_gettime(CLOCK_MONOTONIC, &mono));
+	TEST_SUCC(clock_gettime(CLOCK_BOOTTIME, &boot));
+	TEST_RES(0, diff_ns(&boot, &mono) >= 0);
+}
+END_TEST()
+
+FN_TEST(clock_alarm)
+{
+	struct itimerspec its = { .it_value = { .tv_nsec = 10 * 1000 * 1000 } };
+	struct timespec real, real_alarm, req = { .tv_nsec = 1000 * 1000 };
+	struct sigevent sev = { .sigev_notify = SIGEV_NONE };
+	timer_t timer;
+	uint64_t ticks;
+	int fd;
+
+	TEST_SUCC(clock_gettime(CLOCK_REALTIME_ALARM, &real_alarm));
+	TEST_SUCC(clock_gettime(CLOCK_REALTIME, &real));
+	TEST_RES(0, diff_ns(&real, &real_alarm) >= 0 &&
+			    diff_ns(&real, &real_alarm) < 1000000000LL);
+
+	fd = TEST_SUCC(timerfd_create(CLOCK_BOOTTIME_ALARM, 0));
+	TEST_SUCC(timerfd_settime(fd, 0, &its, NULL));
+	TEST_RES(read(fd, &ticks, sizeof(ticks)),
+		 _ret == sizeof(ticks) && ticks == 1);
+	TEST_SUCC(close(fd));
+
+	TEST_SUCC(timer_create(CLOCK_REALTIME_ALARM, &sev, &timer));
+	TEST_SUCC(timer_delete(timer));
+
+	TEST_SUCC(clock_nanosleep(CLOCK_BOOTTIME_ALARM, 0, &req, NULL));
+	TEST_SUCC(clock_nanosleep(CLOCK_TAI, 0, &req, NULL));
+
+	TEST_ERRNO(timerfd_create(CLOCK_TAI, 0), EINVAL);
+	TEST_ERRNO(timerfd_create(CLOCK_MONOTONIC_RAW, 0), EINVAL);
+}
+END_TEST()
+
+FN_TEST(vdso_clocks)
+{
+	clockid_t clocks[] = { CLOCK_REALTIME, CLOCK_MONOTONIC,
+			       CLOCK_BOOTTIME, CLOCK_TAI };
+	struct timespec before, now, after;
+	size_t i;
+	int j;
+
+	// `clock_gettime` from glibc reads the clocks in the vDSO, so its
+	// results must be consistent with those of the system call.
+	for (i = 0; i < sizeof(clocks) / sizeof(clocks[0]); i++) {
+		for (j = 0; j < 1000; j++) {
+			CHECK(syscall(SYS_clock_gettime, clocks[i], &before));
+			CHECK(clock_gettime(clocks[i], &now));
+			CHECK(syscall(SYS_clock_gettime, clocks[i], &after));
+			if (diff_ns(&now, &before) < 0 ||
+			    diff_ns(&after, &now) < 0)
+				break;
+		}
+		TEST_RES(0, j == 1000);
+	}
+}
+END_TEST()
+
+static void drop_caps(void)
+{
+	struct __user_cap_header_struct hdr = {
+		.version = _LINUX_CAPABILITY_VERSION_3,
+	};
+	struct __user_cap_data_struct capdat[2] = { 0 };
+
+	CHECK(syscall(SYS_capget, &hdr, &capdat));
+
+	capdat[0].effective &= ~(1 << CAP_SYS_TIME);
+	capdat[1].effective &= ~(1 << (CAP_WAKE_ALARM - 32));
+
+	CHECK(syscall(SYS_capset, &hdr, &capdat));
+}
+
+FN_TEST(permission)
+{
+	pid_t pid;
+	int status;
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		struct timespec req = { .tv_nsec = 1000 };
+		struct sigevent sev = { .sigev_notify = SIGEV_NONE };
+		struct timex tx = { .modes = 0 };
+		timer_t timer;
+
+		drop_caps();
+
+		CHECK_WITH(timerfd_create(CLOCK_REALTIME_ALARM, 0),
+			   _ret < 0 && errno == EPERM);
+		CHECK_WITH(timer_create(CLOCK_BOOTTIME_ALARM, &sev, &timer),
+			   _ret < 0 && errno == EPERM);
+		CHECK_WITH(clock_nanosleep(CLOCK_REALTIME_ALARM, 0, &req, NULL),
+			   _ret == EPERM);
+
+		CHECK(adjtimex(&tx));
+		tx.modes = ADJ_OFFSET_SS_READ;
+		CHECK(adjtimex(&tx));
+		tx.modes = ADJ_TAI;
+		tx.constant = 37;
+		CHECK_WITH(adjtimex(&tx), _ret < 0 && errno == EPERM);
+
+		_exit(0);
+	}
+
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) && WEXITSTATUS(status) == 0);
+}
+END_TEST()
@@ -0,0 +1,113 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+#include <linux/futex.h>
+#include <poll.h>
+#include <stdint.h>
+#include <sys/syscall.h>
+#include <sys/timerfd.h>
+#include <time.h>
+#include <unistd.h>
+
+#include "../../common/test.h"
+
+// The timers should expire with a high resolution, instead of at timer ticks (1 ms).
+#define TIMEOUT_NS 50000
+#define MAX_AVERAGE_NS 400000
+#define NR_ROUNDS 20
+
+static int64_t now_ns(void)
+{
+	struct timespec ts;
+
+	clock_gettime(CLOCK_MONOTONIC, &ts);
+	return ts.tv_sec * 1000000000LL + ts.tv_nsec;
+}
+
+static const struct timespec timeout = { .tv_sec = 0, .tv_nsec = TIMEOUT_NS };
+
+static int sleep_once(void)
+{
+	return clock_nanosleep(CLOCK_MONOTONIC, 0, &timeout, NULL);
+}
+
+static int timerfd_once(void)
+{
+	struct itimerspec its = { .it_value = timeout };
+	uint64_t expirations;
+	int fd, ret;
+
+	fd = timerfd_create(CLOCK_MONOTONIC, 0);
+	if (fd < 0)
+		return -1;
+
+	ret = timerfd_settime(fd, 0, &its, NULL);
+	if (ret == 0 && read(fd, &expirations, sizeof(expirations)) !=
+				sizeof(expirations))
+		ret = -1;
+
+	close(fd);
+	return ret;
+}
+
+static int futex_once(void)
+{
+	uint32_t futex = 0;
+
+	if (syscall(SYS_futex, &futex, FUTEX_WAIT_PRIVATE, 0, &timeout, NULL,
+		    0) == 0)
+		return -1;
+	if (errno != ETIMEDOUT)
+		return -1;
+
+	errno = 0;
+	return 0;
+}
+
+static int ppoll_once(void)
+{
+	return ppoll(NULL, 0, &timeout, NULL);
+}
+
+// Returns the average time spent in `func` in nanoseconds, or -1 if `func` fails.
+static int64_t average_ns(int (*func)(void))
+{
+	int64_t start;
+	int i;
+
+	start = now_ns();
+	for (i = 0; i < NR_ROUNDS; i++) {
+		if (func() < 0)
+			return -1;
+	}
+
+	return (now_ns() - start) / NR_ROUNDS;
+}
+
+FN_TEST(nanosleep)
+{
+	TEST_RES(average_ns(sleep_once),
+		 _ret >= TIMEOUT_NS && _ret < MAX_AVERAGE_NS);
+}
+END_TEST()
+
+FN_TEST(timerfd)
+{
+	TEST_RES(average_ns(timerfd_once),
+		 _ret >= TIMEOUT_NS && _ret < MAX_AVERAGE_NS);
+}
+END_TEST()
+
+FN_TEST(futex)
+{
+	TEST_RES(average_ns(futex_once),
+		 _ret >= TIMEOUT_NS && _ret < MAX_AVERAGE_NS);
+}
+END_TEST()
+
+FN_TEST(ppoll)
+{
+	TEST_RES(average_ns(ppoll_once),
+		 _ret >= TIMEOUT_NS && _ret < MAX_AVERAGE_NS);
+}
+END_TEST()
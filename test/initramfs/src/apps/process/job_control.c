@@ -44,10 +44,9 @@ FN_SETUP(run_child)
 	signal(SIGHUP, SIG_IGN);
 	signal(SIGTTIN, SIG_IGN);
 
-	// TODO: We should forbid some TTY operations (e.g., `TIOCSPGRP`) if
-	// the `SIGTTOU` signal is not blocked or ignored and the current
-	// process is not in the foreground process group. However, this is
-	// not currently implemented in Asterinas yet.
+	// Some TTY operations (e.g., `TIOCSPGRP`) will send `SIGTTOU` if
+	// the current process is not in the foreground process group.
+	// Ignore the signal so that these operations can succeed.
 	signal(SIGTTOU, SIG_IGN);
 }
 END_SETUP()
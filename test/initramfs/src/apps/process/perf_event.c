@@ -0,0 +1,197 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <linux/perf_event.h>
+#include <stdint.h>
+#include <string.h>
+#include <sys/ioctl.h>
+#include <sys/mman.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#include "../common/test.h"
+
+#define PAGE_SIZE 4096
+#define DATA_PAGES 4
+
+static int perf_event_open(struct perf_event_attr *attr, pid_t pid, int cpu,
+			   int group_fd, unsigned long flags)
+{
+	return syscall(SYS_perf_event_open, attr, pid, cpu, group_fd, flags);
+}
+
+static void init_attr(struct perf_event_attr *attr, __u32 type, __u64 config)
+{
+	memset(attr, 0, sizeof(*attr));
+	attr->size = sizeof(*attr);
+	attr->type = type;
+	attr->config = config;
+	attr->exclude_kernel = 1;
+}
+
+static void touch_pages(size_t nr_pages)
+{
+	char *buf = mmap(NULL, nr_pages * PAGE_SIZE, PROT_READ | PROT_WRITE,
+			 MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+	if (buf == MAP_FAILED)
+		return;
+	for (size_t i = 0; i < nr_pages; i++)
+		((volatile char *)buf)[i * PAGE_SIZE] = 1;
+	munmap(buf, nr_pages * PAGE_SIZE);
+}
+
+struct read_value {
+	uint64_t value;
+	uint64_t time_enabled;
+	uint64_t time_running;
+	uint64_t id;
+};
+
+FN_TEST(count_page_faults)
+{
+	struct perf_event_attr attr;
+	struct read_value value;
+	uint64_t id;
+	int fd;
+
+	init_attr(&attr, PERF_TYPE_SOFTWARE, PERF_COUNT_SW_PAGE_FAULTS);
+	attr.disabled = 1;
+	attr.read_format = PERF_FORMAT_TOTAL_TIME_ENABLED |
+			   PERF_FORMAT_TOTAL_TIME_RUNNING | PERF_FORMAT_ID;
+	fd = TEST_SUCC(perf_event_open(&attr, 0, -1, -1, PERF_FLAG_FD_CLOEXEC));
+
+	// A disabled event does not count.
+	touch_pages(8);
+	TEST_RES(read(fd, &value, sizeof(value)),
+		 _ret == sizeof(value) && value.value == 0 &&
+			 value.time_enabled == 0);
+
+	TEST_SUCC(ioctl(fd, PERF_EVENT_IOC_ENABLE, 0));
+	touch_pages(8);
+	TEST_SUCC(ioctl(fd, PERF_EVENT_IOC_DISABLE, 0));
+	TEST_RES(read(fd, &value, sizeof(value)),
+		 _ret == sizeof(value) && value.value >= 8 &&
+			 value.time_running <= value.time_enabled);
+
+	TEST_RES(ioctl(fd, PERF_EVENT_IOC_ID, &id), id == value.id);
+
+	TEST_SUCC(ioctl(fd, PERF_EVENT_IOC_RESET, 0));
+	TEST_RES(read(fd, &value, sizeof(value)),
+		 _ret == sizeof(value) && value.value == 0);
+
+	// The buffer is too small.
+	TEST_ERRNO(read(fd, &value, sizeof(value.value)), ENOSPC);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(count_task_clock)
+{
+	struct perf_event_attr attr;
+	uint64_t value;
+	int fd;
+
+	init_attr(&attr, PERF_TYPE_SOFTWARE, PERF_COUNT_SW_TASK_CLOCK);
+	fd = TEST_SUCC(perf_event_open(&attr, 0, -1, -1, 0));
+
+	for (volatile int i = 0; i < 10000000; i++)
+		;
+	TEST_RES(read(fd, &value, sizeof(value)),
+		 _ret == sizeof(value) && value > 0);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(count_hardware_events)
+{
+	struct perf_event_attr attr;
+	uint64_t value;
+	int fd;
+
+	init_attr(&attr, PERF_TYPE_HARDWARE, PERF_COUNT_HW_INSTRUCTIONS);
+	fd = perf_event_open(&attr, 0, -1, -1, 0);
+	if (fd < 0) {
+		// The PMU is not available (e.g., in a virtual machine).
+		TEST_ERRNO(perf_event_open(&attr, 0, -1, -1, 0), ENOENT);
+	} else {
+		for (volatile int i = 0; i < 1000000; i++)
+			;
+		TEST_RES(read(fd, &value, sizeof(value)),
+			 _ret == sizeof(value) && value > 0);
+		TEST_SUCC(close(fd));
+	}
+}
+END_TEST()
+
+FN_TEST(sample_page_faults)
+{
+	struct perf_event_attr attr;
+	struct perf_event_mmap_page *meta;
+	size_t len = (1 + DATA_PAGES) * PAGE_SIZE;
+	int fd;
+
+	init_attr(&attr, PERF_TYPE_SOFTWARE, PERF_COUNT_SW_PAGE_FAULTS);
+	attr.sample_period = 1;
+	attr.sample_type = PERF_SAMPLE_IP | PERF_SAMPLE_TID | PERF_SAMPLE_ADDR;
+	attr.disabled = 1;
+	fd = TEST_SUCC(perf_event_open(&attr, 0, -1, -1, 0));
+
+	// The ring buffer must be shared and mapped at zero.
+	TEST_ERRNO(mmap(NULL, len, PROT_READ | PROT_WRITE, MAP_PRIVATE, fd, 0),
+		   EINVAL);
+	TEST_ERRNO(mmap(NULL, len, PROT_READ | PROT_WRITE, MAP_SHARED, fd,
+			PAGE_SIZE),
+		   EINVAL);
+	meta = TEST_SUCC(
+		mmap(NULL, len, PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0));
+
+	TEST_RES(meta->data_head, meta->data_head == 0);
+	TEST_SUCC(ioctl(fd, PERF_EVENT_IOC_ENABLE, 0));
+	touch_pages(4);
+	TEST_SUCC(ioctl(fd, PERF_EVENT_IOC_DISABLE, 0));
+	TEST_RES(meta->data_head,
+		 meta->data_head > 0 &&
+			 meta->data_head <= DATA_PAGES * PAGE_SIZE);
+
+	TEST_SUCC(munmap(meta, len));
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(invalid_arguments)
+{
+	struct perf_event_attr attr;
+	int fd;
+
+	init_attr(&attr, PERF_TYPE_SOFTWARE, PERF_COUNT_SW_CPU_CLOCK);
+
+	// Neither a thread nor a CPU is specified.
+	TEST_ERRNO(perf_event_open(&attr, -1, -1, -1, 0), EINVAL);
+	// The CPU does not exist.
+	TEST_ERRNO(perf_event_open(&attr, 0, 100000, -1, 0), EINVAL);
+	// The flags are invalid.
+	TEST_ERRNO(perf_event_open(&attr, 0, -1, -1, 1 << 10), EINVAL);
+
+	// The group file is not a perf event file.
+	TEST_ERRNO(perf_event_open(&attr, 0, -1, STDIN_FILENO, 0), EBADF);
+
+	// The type is unknown.
+	attr.type = 100;
+	TEST_ERRNO(perf_event_open(&attr, 0, -1, -1, 0), ENOENT);
+
+	// The size is too large.
+	init_attr(&attr, PERF_TYPE_SOFTWARE, PERF_COUNT_SW_CPU_CLOCK);
+	attr.size = PAGE_SIZE + 1;
+	TEST_ERRNO(perf_event_open(&attr, 0, -1, -1, 0), E2BIG);
+	TEST_RES(attr.size, attr.size < PAGE_SIZE);
+
+	// The ioctl command is not supported.
+	init_attr(&attr, PERF_TYPE_SOFTWARE, PERF_COUNT_SW_CPU_CLOCK);
+	fd = TEST_SUCC(perf_event_open(&attr, 0, -1, -1, 0));
+	TEST_ERRNO(ioctl(fd, TCGETS), ENOTTY);
+	TEST_SUCC(close(fd));
+}
+END_TEST()
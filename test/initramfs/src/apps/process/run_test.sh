@@ -5,6 +5,7 @@
 set -e
 
 ./cgroup.sh
+./cgroup/io_controller
 
 ./clone3/clone_exit_signal
 ./clone3/clone_files
@@ -13,9 +14,11 @@ set -e
 ./clone3/clone_process
 
 ./cpu_affinity/cpu_affinity
+./cpu_affinity/cpu_migrate
 
 ./execve/execve
 ./execve/execve_err
+./execve/execve_interp
 ./execve/execve_memfd
 ./execve/execve_mt_parent
 
@@ -29,6 +32,8 @@ set -e
 
 ./getpid/getpid
 
+./itimer/clocks
+./itimer/hrtimer
 ./itimer/setitimer
 ./itimer/timer_create
 
@@ -57,6 +62,7 @@ fi
 
 ./group_session
 ./job_control
+./perf_event
 ./pidfd
 ./pidfd_getfd
 ./wait4
@@ -0,0 +1,340 @@
+// SPDX-License-Identifier: MPL-2.0
+
+// The policy is loaded as text, so this test cannot run on Linux, where the
+// policy must be compiled into the binary format by `apparmor_parser`.
+
+#define _GNU_SOURCE
+
+#include <fcntl.h>
+#include <sys/resource.h>
+#include <sys/socket.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "../../common/test.h"
+
+#define APPARMOR_DIR "/sys/kernel/security/apparmor"
+#define TEST_DIR "/tmp/apparmor"
+#define ATTACHED_EXE TEST_DIR "/attached"
+
+#define MODE_ENV "APPARMOR_TEST_MODE"
+
+// The rules that allow the dynamic loader to load the shared libraries.
+#define LIB_RULES                    \
+	"  /nix/store/** rm,\n"      \
+	"  /lib/** rm,\n"            \
+	"  /lib64/** rm,\n"          \
+	"  /usr/lib/** rm,\n"        \
+	"  /etc/ld.so.cache r,\n"    \
+	"  /proc/*/attr/current r,\n"
+
+#define POLICY                                  \
+	"# The profile for `exec aa_enforce`.\n" \
+	"profile aa_enforce {\n" LIB_RULES      \
+	"  " TEST_DIR "/allowed rw,\n"          \
+	"  " TEST_DIR "/{new_file,dir/} w,\n"   \
+	"  deny " TEST_DIR "/denied w,\n"       \
+	"  " TEST_DIR "/denied r,\n"            \
+	"  capability sys_resource,\n"          \
+	"  network inet stream,\n"              \
+	"}\n"                                   \
+	"profile aa_complain flags=(complain) {\n" LIB_RULES \
+	"  deny " TEST_DIR "/denied w,\n"       \
+	"  deny capability sys_admin,\n"        \
+	"}\n"                                   \
+	ATTACHED_EXE " {\n" LIB_RULES           \
+	"  " TEST_DIR "/other r,\n"             \
+	"}\n"
+
+static ssize_t write_str(const char *path, const char *str)
+{
+	int fd, saved_errno;
+	ssize_t ret;
+
+	fd = open(path, O_WRONLY);
+	if (fd < 0)
+		return -1;
+
+	ret = write(fd, str, strlen(str));
+	saved_errno = errno;
+	close(fd);
+	errno = saved_errno;
+
+	return ret;
+}
+
+static ssize_t read_str(const char *path, char *buf, size_t size)
+{
+	int fd, saved_errno;
+	ssize_t ret;
+
+	fd = open(path, O_RDONLY);
+	if (fd < 0)
+		return -1;
+
+	ret = read(fd, buf, size - 1);
+	saved_errno = errno;
+	close(fd);
+	errno = saved_errno;
+
+	if (ret >= 0)
+		buf[ret] = '\0';
+	return ret;
+}
+
+static int open_and_close(const char *path, int flags)
+{
+	int fd;
+
+	fd = open(path, flags, 0644);
+	if (fd < 0)
+		return -1;
+	return close(fd);
+}
+
+static int raise_nofile_limit(void)
+{
+	struct rlimit rlimit;
+
+	if (getrlimit(RLIMIT_NOFILE, &rlimit) < 0)
+		return -1;
+
+	rlimit.rlim_max = rlimit.rlim_cur;
+	if (setrlimit(RLIMIT_NOFILE, &rlimit) < 0)
+		return -1;
+
+	rlimit.rlim_max += 1;
+	return setrlimit(RLIMIT_NOFILE, &rlimit);
+}
+
+static void check_current(const char *expected)
+{
+	char buf[128];
+
+	CHECK(read_str("/proc/self/attr/current", buf, sizeof(buf)));
+	CHECK_WITH(strcmp(buf, expected), _ret == 0);
+}
+
+static void run_enforce(void)
+{
+	check_current("aa_enforce (enforce)\n");
+
+	CHECK(open_and_close(TEST_DIR "/allowed", O_RDWR));
+	CHECK_WITH(open_and_close(TEST_DIR "/other", O_RDONLY),
+		   _ret == -1 && errno == EACCES);
+
+	// The deny rules take precedence over the allow rules.
+	CHECK(open_and_close(TEST_DIR "/denied", O_RDONLY));
+	CHECK_WITH(open_and_close(TEST_DIR "/denied", O_WRONLY),
+		   _ret == -1 && errno == EACCES);
+
+	CHECK(open_and_close(TEST_DIR "/new_file", O_WRONLY | O_CREAT));
+	CHECK(unlink(TEST_DIR "/new_file"));
+	CHECK(mkdir(TEST_DIR "/dir", 0755));
+	CHECK(rmdir(TEST_DIR "/dir"));
+	CHECK_WITH(mkdir(TEST_DIR "/other_dir", 0755),
+		   _ret == -1 && errno == EACCES);
+	CHECK_WITH(rename(TEST_DIR "/allowed", TEST_DIR "/renamed"),
+		   _ret == -1 && errno == EACCES);
+
+	CHECK(raise_nofile_limit());
+	CHECK_WITH(sethostname("apparmor", 8), _ret == -1 && errno == EPERM);
+
+	CHECK(close(CHECK(socket(AF_INET, SOCK_STREAM, 0))));
+	CHECK_WITH(socket(AF_INET, SOCK_DGRAM, 0),
+		   _ret == -1 && errno == EACCES);
+	CHECK_WITH(socket(AF_UNIX, SOCK_STREAM, 0),
+		   _ret == -1 && errno == EACCES);
+
+	// The profile has no rule to execute the file.
+	CHECK_WITH(execl(ATTACHED_EXE, ATTACHED_EXE, NULL),
+		   _ret == -1 && errno == EACCES);
+}
+
+static void run_complain(void)
+{
+	check_current("aa_complain (complain)\n");
+
+	CHECK(open_and_close(TEST_DIR "/other", O_RDONLY));
+	CHECK(open_and_close(TEST_DIR "/denied", O_RDONLY));
+	CHECK_WITH(open_and_close(TEST_DIR "/denied", O_WRONLY),
+		   _ret == -1 && errno == EACCES);
+
+	CHECK(raise_nofile_limit());
+	CHECK_WITH(sethostname("apparmor", 8), _ret == -1 && errno == EPERM);
+
+	CHECK(close(CHECK(socket(AF_INET, SOCK_DGRAM, 0))));
+}
+
+static void run_attached(void)
+{
+	check_current(ATTACHED_EXE " (enforce)\n");
+
+	CHECK(open_and_close(TEST_DIR "/other", O_RDONLY));
+	CHECK_WITH(open_and_close(TEST_DIR "/allowed", O_RDONLY),
+		   _ret == -1 && errno == EACCES);
+}
+
+// This runs before the other setup and test functions, so that a confined
+// child only runs the checks for its profile.
+FN_SETUP(confined)
+{
+	const char *mode;
+
+	mode = getenv(MODE_ENV);
+	if (mode == NULL)
+		return;
+
+	if (strcmp(mode, "enforce") == 0)
+		run_enforce();
+	else if (strcmp(mode, "complain") == 0)
+		run_complain();
+	else if (strcmp(mode, "attached") == 0)
+		run_attached();
+	else
+		exit(EXIT_FAILURE);
+
+	exit(EXIT_SUCCESS);
+}
+END_SETUP()
+
+static void copy_file(const char *src, const char *dst)
+{
+	char buf[4096];
+	int src_fd, dst_fd;
+	ssize_t len;
+
+	src_fd = CHECK(open(src, O_RDONLY));
+	dst_fd = CHECK(open(dst, O_WRONLY | O_CREAT | O_TRUNC, 0755));
+	while ((len = CHECK(read(src_fd, buf, sizeof(buf)))) > 0)
+		CHECK_WITH(write(dst_fd, buf, len), _ret == len);
+	CHECK(close(src_fd));
+	CHECK(close(dst_fd));
+}
+
+FN_SETUP(files)
+{
+	CHECK(mkdir(TEST_DIR, 0755));
+	CHECK(open_and_close(TEST_DIR "/allowed", O_WRONLY | O_CREAT));
+	CHECK(open_and_close(TEST_DIR "/denied", O_WRONLY | O_CREAT));
+	CHECK(open_and_close(TEST_DIR "/other", O_WRONLY | O_CREAT));
+	copy_file("/proc/self/exe", ATTACHED_EXE);
+}
+END_SETUP()
+
+FN_TEST(load_invalid)
+{
+	TEST_ERRNO(write_str(APPARMOR_DIR "/.load", "profile p"), EINVAL);
+	TEST_ERRNO(write_str(APPARMOR_DIR "/.load", "profile p {"), EINVAL);
+	TEST_ERRNO(write_str(APPARMOR_DIR "/.load", "profile p { /a r }"),
+		   EINVAL);
+	TEST_ERRNO(write_str(APPARMOR_DIR "/.load", "profile p { /a z, }"),
+		   EINVAL);
+	TEST_ERRNO(write_str(APPARMOR_DIR "/.load", "profile p { /a x, }"),
+		   EINVAL);
+	TEST_ERRNO(write_str(APPARMOR_DIR "/.load", "profile p { /{a r, }"),
+		   EINVAL);
+	TEST_ERRNO(write_str(APPARMOR_DIR "/.load",
+			     "profile p { capability foo, }"),
+		   EINVAL);
+	TEST_ERRNO(write_str(APPARMOR_DIR "/.load",
+			     "profile p flags=(foo) { }"),
+		   EINVAL);
+	TEST_ERRNO(write_str(APPARMOR_DIR "/.load", "# only a comment"),
+		   EINVAL);
+	TEST_ERRNO(write_str(APPARMOR_DIR "/.remove", "p"), ENOENT);
+}
+END_TEST()
+
+FN_TEST(load)
+{
+	char buf[256];
+
+	TEST_RES(write_str(APPARMOR_DIR "/.load", POLICY),
+		 _ret == strlen(POLICY));
+	TEST_ERRNO(write_str(APPARMOR_DIR "/.load", POLICY), EEXIST);
+	TEST_RES(write_str(APPARMOR_DIR "/.replace", POLICY),
+		 _ret == strlen(POLICY));
+
+	TEST_RES(read_str(APPARMOR_DIR "/profiles", buf, sizeof(buf)),
+		 strcmp(buf, ATTACHED_EXE " (enforce)\n"
+			     "aa_complain (complain)\n"
+			     "aa_enforce (enforce)\n") == 0);
+}
+END_TEST()
+
+FN_TEST(proc_attr)
+{
+	char buf[128];
+
+	TEST_RES(read_str("/proc/self/attr/current", buf, sizeof(buf)),
+		 strcmp(buf, "unconfined\n") == 0);
+	TEST_RES(read_str("/proc/self/attr/exec", buf, sizeof(buf)),
+		 _ret == 0);
+
+	TEST_ERRNO(write_str("/proc/self/attr/exec", "exec no_such_profile"),
+		   ENOENT);
+	TEST_ERRNO(write_str("/proc/self/attr/exec", "changehat aa_enforce"),
+		   EINVAL);
+	TEST_ERRNO(write_str("/proc/1/attr/exec", "exec aa_enforce"), EACCES);
+}
+END_TEST()
+
+static int run_confined(const char *profile, const char *mode,
+			const char *path)
+{
+	char command[64], env[64];
+	char *const argv[] = { (char *)path, NULL };
+	char *const envp[] = { env, NULL };
+	pid_t pid;
+	int status;
+
+	pid = CHECK(fork());
+	if (pid == 0) {
+		if (profile != NULL) {
+			snprintf(command, sizeof(command), "exec %s", profile);
+			CHECK(write_str("/proc/self/attr/exec", command));
+		}
+		snprintf(env, sizeof(env), MODE_ENV "=%s", mode);
+		CHECK(execve(path, argv, envp));
+		exit(EXIT_FAILURE);
+	}
+
+	CHECK_WITH(waitpid(pid, &status, 0), _ret == pid);
+	return WIFEXITED(status) ? WEXITSTATUS(status) : -1;
+}
+
+FN_TEST(confine)
+{
+	TEST_RES(run_confined("aa_enforce", "enforce", "/proc/self/exe"),
+		 _ret == 0);
+	TEST_RES(run_confined("aa_complain", "complain", "/proc/self/exe"),
+		 _ret == 0);
+	TEST_RES(run_confined(NULL, "attached", ATTACHED_EXE), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(remove)
+{
+	char buf[256];
+
+	TEST_SUCC(write_str(APPARMOR_DIR "/.remove", "aa_enforce"));
+	TEST_SUCC(write_str(APPARMOR_DIR "/.remove", "aa_complain\n"));
+	TEST_SUCC(write_str(APPARMOR_DIR "/.remove", ATTACHED_EXE));
+	TEST_ERRNO(write_str(APPARMOR_DIR "/.remove", "aa_enforce"), ENOENT);
+
+	TEST_RES(read_str(APPARMOR_DIR "/profiles", buf, sizeof(buf)),
+		 _ret == 0);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(unlink(ATTACHED_EXE));
+	CHECK(unlink(TEST_DIR "/allowed"));
+	CHECK(unlink(TEST_DIR "/denied"));
+	CHECK(unlink(TEST_DIR "/other"));
+	CHECK(rmdir(TEST_DIR));
+}
+END_SETUP()
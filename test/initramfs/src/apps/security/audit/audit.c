@@ -0,0 +1,256 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+#include <linux/audit.h>
+#include <linux/netlink.h>
+#include <poll.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "../../common/test.h"
+
+#define RULE_KEY "getpid_key"
+#define RULE_KEY_LEN (sizeof(RULE_KEY) - 1)
+#define RULE_LEN (sizeof(struct audit_rule_data) + RULE_KEY_LEN)
+
+static int sk;
+static int seq;
+static char buf[16384] __attribute__((aligned(NLMSG_ALIGNTO)));
+
+static int send_request(int type, int flags, const void *payload, size_t len)
+{
+	char req[NLMSG_SPACE(sizeof(struct audit_rule_data) + 64)]
+		__attribute__((aligned(NLMSG_ALIGNTO)));
+	struct nlmsghdr *hdr = (struct nlmsghdr *)req;
+
+	memset(req, 0, sizeof(req));
+	hdr->nlmsg_len = NLMSG_LENGTH(len);
+	hdr->nlmsg_type = type;
+	hdr->nlmsg_flags = NLM_F_REQUEST | flags;
+	hdr->nlmsg_seq = ++seq;
+	memcpy(NLMSG_DATA(hdr), payload, len);
+
+	return send(sk, req, hdr->nlmsg_len, 0);
+}
+
+// Receives the next message of the type, skipping the other messages (e.g.,
+// the records of the configuration changes). Linux sends the records
+// asynchronously, so this waits for a while.
+static int recv_type(int type)
+{
+	struct nlmsghdr *hdr = (struct nlmsghdr *)buf;
+	struct pollfd pfd = { .fd = sk, .events = POLLIN };
+	int len;
+
+	for (;;) {
+		if (poll(&pfd, 1, 1000) == 0) {
+			errno = EAGAIN;
+			return -1;
+		}
+		len = recv(sk, buf, sizeof(buf) - 1, 0);
+		if (len < 0)
+			return -1;
+		if (hdr->nlmsg_type == type)
+			break;
+	}
+
+	// Records are not null-terminated
+	buf[len] = 0;
+	return len;
+}
+
+// Receives the next record of the system call
+static int recv_syscall(int nr)
+{
+	char field[32];
+	int len;
+
+	snprintf(field, sizeof(field), " syscall=%d ", nr);
+	do {
+		len = recv_type(AUDIT_SYSCALL);
+	} while (len >= 0 && !strstr(NLMSG_DATA((struct nlmsghdr *)buf), field));
+
+	return len;
+}
+
+// Receives the acknowledgment and returns its error code
+static int recv_ack(void)
+{
+	struct nlmsgerr *err = NLMSG_DATA((struct nlmsghdr *)buf);
+
+	if (recv_type(NLMSG_ERROR) < 0)
+		return 1;
+	return err->error;
+}
+
+static struct audit_status *get_status(void)
+{
+	if (send_request(AUDIT_GET, 0, NULL, 0) < 0 ||
+	    recv_type(AUDIT_GET) < (int)NLMSG_LENGTH(sizeof(struct audit_status)))
+		return NULL;
+	return NLMSG_DATA((struct nlmsghdr *)buf);
+}
+
+static int set_status(int mask, int enabled, int pid)
+{
+	struct audit_status status = { .mask = mask };
+
+	status.enabled = enabled;
+	status.pid = pid;
+	if (send_request(AUDIT_SET, NLM_F_ACK, &status, sizeof(status)) < 0)
+		return 1;
+	return recv_ack();
+}
+
+static char rule_buf[RULE_LEN] __attribute__((aligned(NLMSG_ALIGNTO)));
+static struct audit_rule_data *rule = (struct audit_rule_data *)rule_buf;
+
+static int send_rule(int type, int flags)
+{
+	if (send_request(type, flags, rule_buf, RULE_LEN) < 0)
+		return 1;
+	return recv_ack();
+}
+
+FN_SETUP(socket)
+{
+	sk = CHECK(socket(PF_NETLINK, SOCK_RAW | SOCK_NONBLOCK, NETLINK_AUDIT));
+}
+END_SETUP()
+
+FN_TEST(status)
+{
+	struct sockaddr_nl addr = { .nl_family = AF_NETLINK, .nl_groups = 1 };
+	int sk_group;
+
+	TEST_RES(get_status(), _ret != NULL && _ret->enabled == 0 &&
+				       _ret->pid == 0);
+
+	TEST_RES(set_status(AUDIT_STATUS_PID, 0, getpid() + 1),
+		 _ret == -EINVAL);
+	TEST_RES(set_status(AUDIT_STATUS_ENABLED, 3, 0), _ret == -EINVAL);
+	TEST_RES(set_status(AUDIT_STATUS_ENABLED | AUDIT_STATUS_PID, 1,
+			    getpid()),
+		 _ret == 0);
+
+	TEST_RES(get_status(), _ret != NULL && _ret->enabled == 1 &&
+				       _ret->pid == getpid());
+
+	// Root has the `CAP_AUDIT_READ` capability to join the multicast group
+	sk_group = TEST_SUCC(socket(PF_NETLINK, SOCK_RAW, NETLINK_AUDIT));
+	TEST_SUCC(bind(sk_group, (struct sockaddr *)&addr, sizeof(addr)));
+	TEST_SUCC(close(sk_group));
+}
+END_TEST()
+
+FN_SETUP(rule)
+{
+	rule->flags = AUDIT_FILTER_EXIT;
+	rule->action = AUDIT_ALWAYS;
+	rule->mask[SYS_getpid / 32] = 1 << (SYS_getpid % 32);
+	rule->field_count = 1;
+	rule->fields[0] = AUDIT_FILTERKEY;
+	rule->values[0] = RULE_KEY_LEN;
+	rule->fieldflags[0] = AUDIT_EQUAL;
+	rule->buflen = RULE_KEY_LEN;
+	memcpy(rule->buf, RULE_KEY, RULE_KEY_LEN);
+}
+END_SETUP()
+
+FN_TEST(add_rule)
+{
+	pid_t pid;
+
+	TEST_RES(send_rule(AUDIT_ADD_RULE, NLM_F_ACK), _ret == 0);
+	TEST_RES(send_rule(AUDIT_ADD_RULE, NLM_F_ACK), _ret == -EEXIST);
+
+	// Linux audits only the threads that are created after auditing is enabled
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		syscall(SYS_getpid);
+		_exit(0);
+	}
+	TEST_RES(waitpid(pid, NULL, 0), _ret == pid);
+	TEST_RES(recv_syscall(SYS_getpid),
+		 _ret > 0 && strstr(NLMSG_DATA((struct nlmsghdr *)buf),
+				    " key=\"" RULE_KEY "\"") != NULL);
+	TEST_RES(recv_type(AUDIT_EOE), _ret > 0);
+}
+END_TEST()
+
+FN_TEST(list_rules)
+{
+	struct audit_rule_data *data = NLMSG_DATA((struct nlmsghdr *)buf);
+
+	TEST_SUCC(send_request(AUDIT_LIST_RULES, 0, NULL, 0));
+	TEST_RES(recv_type(AUDIT_LIST_RULES),
+		 _ret > 0 &&
+			 ((struct nlmsghdr *)buf)->nlmsg_len ==
+				 NLMSG_LENGTH(RULE_LEN) &&
+			 data->flags == AUDIT_FILTER_EXIT &&
+			 data->action == AUDIT_ALWAYS &&
+			 data->buflen == RULE_KEY_LEN &&
+			 memcmp(data->buf, RULE_KEY, RULE_KEY_LEN) == 0);
+	TEST_RES(recv_type(NLMSG_DONE), _ret > 0);
+}
+END_TEST()
+
+FN_TEST(del_rule)
+{
+	pid_t pid;
+
+	TEST_RES(send_rule(AUDIT_DEL_RULE, NLM_F_ACK), _ret == 0);
+	TEST_RES(send_rule(AUDIT_DEL_RULE, NLM_F_ACK), _ret == -ENOENT);
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		syscall(SYS_getpid);
+		_exit(0);
+	}
+	TEST_RES(waitpid(pid, NULL, 0), _ret == pid);
+	TEST_ERRNO(recv_syscall(SYS_getpid), EAGAIN);
+}
+END_TEST()
+
+FN_TEST(invalid_rule)
+{
+	// `AUDIT_FILTER_WATCH` is not a valid list
+	rule->flags = 3;
+	TEST_RES(send_rule(AUDIT_ADD_RULE, NLM_F_ACK), _ret == -EINVAL);
+	rule->flags = AUDIT_FILTER_EXIT;
+
+	// The filter key is longer than the buffer
+	rule->values[0] = RULE_KEY_LEN + 1;
+	TEST_RES(send_rule(AUDIT_ADD_RULE, NLM_F_ACK), _ret == -EINVAL);
+	rule->values[0] = RULE_KEY_LEN;
+}
+END_TEST()
+
+FN_TEST(user_message)
+{
+	static const char msg[] = "hello";
+
+	TEST_SUCC(send_request(AUDIT_USER, 0, msg, sizeof(msg)));
+	TEST_RES(recv_type(AUDIT_USER),
+		 _ret > 0 && strstr(NLMSG_DATA((struct nlmsghdr *)buf),
+				    " msg='hello'") != NULL);
+}
+END_TEST()
+
+FN_TEST(disable)
+{
+	TEST_RES(set_status(AUDIT_STATUS_ENABLED | AUDIT_STATUS_PID, 0, 0),
+		 _ret == 0);
+	TEST_RES(get_status(), _ret != NULL && _ret->enabled == 0 &&
+				       _ret->pid == 0);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(sk));
+}
+END_SETUP()
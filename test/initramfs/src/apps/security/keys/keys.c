@@ -0,0 +1,343 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <linux/keyctl.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "../../common/test.h"
+
+typedef int32_t key_serial_t;
+
+static key_serial_t add_key(const char *type, const char *description,
+			    const void *payload, size_t plen,
+			    key_serial_t keyring)
+{
+	return syscall(SYS_add_key, type, description, payload, plen, keyring);
+}
+
+static key_serial_t request_key(const char *type, const char *description,
+				const char *callout_info,
+				key_serial_t dest_keyring)
+{
+	return syscall(SYS_request_key, type, description, callout_info,
+		       dest_keyring);
+}
+
+static long keyctl(int operation, unsigned long arg2, unsigned long arg3,
+		   unsigned long arg4, unsigned long arg5)
+{
+	return syscall(SYS_keyctl, operation, arg2, arg3, arg4, arg5);
+}
+
+#define PERM_ALL 0x3f3f3f3f
+
+static key_serial_t session;
+static key_serial_t ring;
+
+FN_SETUP(session)
+{
+	// Use a new session keyring so that the test does not depend on the
+	// keyrings of the caller.
+	session = CHECK(keyctl(KEYCTL_JOIN_SESSION_KEYRING, 0, 0, 0, 0));
+	ring = CHECK(add_key("keyring", "test_ring", NULL, 0,
+			     KEY_SPEC_SESSION_KEYRING));
+}
+END_SETUP()
+
+FN_TEST(special_keyrings)
+{
+	TEST_RES(keyctl(KEYCTL_GET_KEYRING_ID, KEY_SPEC_SESSION_KEYRING, 0, 0,
+			0),
+		 _ret == session);
+	TEST_ERRNO(keyctl(KEYCTL_GET_KEYRING_ID, KEY_SPEC_THREAD_KEYRING, 0, 0,
+			  0),
+		   ENOKEY);
+	TEST_RES(keyctl(KEYCTL_GET_KEYRING_ID, KEY_SPEC_THREAD_KEYRING, 1, 0,
+			0),
+		 _ret > 0);
+	TEST_RES(keyctl(KEYCTL_GET_KEYRING_ID, KEY_SPEC_USER_KEYRING, 0, 0, 0),
+		 _ret > 0);
+	TEST_ERRNO(keyctl(KEYCTL_GET_KEYRING_ID, KEY_SPEC_REQKEY_AUTH_KEY, 0,
+			  0, 0),
+		   ENOKEY);
+	TEST_ERRNO(keyctl(KEYCTL_GET_KEYRING_ID, 0, 0, 0, 0), EINVAL);
+	TEST_ERRNO(keyctl(KEYCTL_GET_KEYRING_ID, -9, 0, 0, 0), EINVAL);
+	TEST_ERRNO(keyctl(KEYCTL_GET_KEYRING_ID, 0x7fffffff, 0, 0, 0), ENOKEY);
+}
+END_TEST()
+
+FN_TEST(add_key_errors)
+{
+	TEST_ERRNO(add_key("no_such_type", "desc", "data", 4, ring), ENODEV);
+	TEST_ERRNO(add_key(".internal", "desc", "data", 4, ring), EPERM);
+	TEST_ERRNO(add_key("", "desc", "data", 4, ring), EINVAL);
+	TEST_ERRNO(add_key("keyring", ".internal", NULL, 0, ring), EPERM);
+	TEST_ERRNO(add_key("user", "", "data", 4, ring), EINVAL);
+	TEST_ERRNO(add_key("user", NULL, "data", 4, ring), EINVAL);
+	TEST_ERRNO(add_key("user", "desc", NULL, 0, ring), EINVAL);
+	TEST_ERRNO(add_key("keyring", "desc", "data", 4, ring), EINVAL);
+	TEST_ERRNO(add_key("logon", "no_service", "data", 4, ring), EINVAL);
+	TEST_ERRNO(add_key("logon", ":desc", "data", 4, ring), EINVAL);
+	TEST_ERRNO(add_key("user", "desc", "data", 1024 * 1024, ring), EINVAL);
+}
+END_TEST()
+
+FN_TEST(user_key)
+{
+	key_serial_t key;
+	char buf[64];
+
+	key = TEST_RES(add_key("user", "user_key", "data", 4, ring), _ret > 0);
+	TEST_RES(keyctl(KEYCTL_READ, key, (unsigned long)buf, sizeof(buf), 0),
+		 _ret == 4 && memcmp(buf, "data", 4) == 0);
+	TEST_RES(keyctl(KEYCTL_READ, key, 0, 0, 0), _ret == 4);
+
+	// Adding a key of the same description updates the existing key.
+	TEST_RES(add_key("user", "user_key", "new_data", 8, ring), _ret == key);
+	TEST_RES(keyctl(KEYCTL_READ, key, (unsigned long)buf, sizeof(buf), 0),
+		 _ret == 8 && memcmp(buf, "new_data", 8) == 0);
+
+	TEST_SUCC(keyctl(KEYCTL_UPDATE, key, (unsigned long)"abc", 3, 0));
+	TEST_RES(keyctl(KEYCTL_READ, key, (unsigned long)buf, sizeof(buf), 0),
+		 _ret == 3 && memcmp(buf, "abc", 3) == 0);
+	TEST_ERRNO(keyctl(KEYCTL_UPDATE, key, 0, 0, 0), EINVAL);
+	TEST_ERRNO(keyctl(KEYCTL_UPDATE, ring, (unsigned long)"abc", 3, 0),
+		   EOPNOTSUPP);
+
+	// The buffer is not filled if it is too small.
+	memset(buf, 'x', sizeof(buf));
+	TEST_RES(keyctl(KEYCTL_READ, key, (unsigned long)buf, 2, 0),
+		 _ret == 3 && buf[0] == 'x');
+
+	TEST_SUCC(keyctl(KEYCTL_UNLINK, key, ring, 0, 0));
+}
+END_TEST()
+
+FN_TEST(logon_key)
+{
+	key_serial_t key;
+	char buf[64];
+
+	key = TEST_RES(add_key("logon", "svc:logon_key", "secret", 6, ring),
+		       _ret > 0);
+	TEST_ERRNO(keyctl(KEYCTL_READ, key, (unsigned long)buf, sizeof(buf), 0),
+		   EOPNOTSUPP);
+	TEST_RES(request_key("logon", "svc:logon_key", NULL, 0), _ret == key);
+
+	TEST_SUCC(keyctl(KEYCTL_UNLINK, key, ring, 0, 0));
+}
+END_TEST()
+
+FN_TEST(describe)
+{
+	key_serial_t key;
+	char buf[128], expected[128];
+	int len;
+
+	key = TEST_RES(add_key("user", "desc_key", "data", 4, ring), _ret > 0);
+	TEST_SUCC(keyctl(KEYCTL_SETPERM, key, PERM_ALL, 0, 0));
+
+	len = snprintf(expected, sizeof(expected), "user;%u;%u;%08x;desc_key",
+		       getuid(), getgid(), PERM_ALL);
+	TEST_RES(keyctl(KEYCTL_DESCRIBE, key, (unsigned long)buf, sizeof(buf),
+			0),
+		 _ret == len + 1 && strcmp(buf, expected) == 0);
+	TEST_RES(keyctl(KEYCTL_DESCRIBE, key, 0, 0, 0), _ret == len + 1);
+
+	TEST_ERRNO(keyctl(KEYCTL_SETPERM, key, 0x40, 0, 0), EINVAL);
+
+	TEST_SUCC(keyctl(KEYCTL_CHOWN, key, -1, 1234, 0));
+	len = snprintf(expected, sizeof(expected), "user;%u;1234;%08x;desc_key",
+		       getuid(), PERM_ALL);
+	TEST_RES(keyctl(KEYCTL_DESCRIBE, key, (unsigned long)buf, sizeof(buf),
+			0),
+		 _ret == len + 1 && strcmp(buf, expected) == 0);
+
+	TEST_SUCC(keyctl(KEYCTL_UNLINK, key, ring, 0, 0));
+}
+END_TEST()
+
+FN_TEST(keyring_links)
+{
+	key_serial_t key, inner;
+	key_serial_t serials[4];
+
+	key = TEST_RES(add_key("user", "link_key", "data", 4, ring), _ret > 0);
+	inner = TEST_RES(add_key("keyring", "inner_ring", NULL, 0, ring),
+			 _ret > 0);
+
+	TEST_RES(keyctl(KEYCTL_READ, ring, (unsigned long)serials,
+			sizeof(serials), 0),
+		 _ret == 2 * sizeof(key_serial_t) && serials[0] == key &&
+			 serials[1] == inner);
+	TEST_ERRNO(keyctl(KEYCTL_READ, ring, (unsigned long)serials, 3, 0),
+		   EINVAL);
+
+	TEST_SUCC(keyctl(KEYCTL_LINK, key, inner, 0, 0));
+	TEST_ERRNO(keyctl(KEYCTL_LINK, ring, inner, 0, 0), EDEADLK);
+	TEST_ERRNO(keyctl(KEYCTL_LINK, ring, ring, 0, 0), EDEADLK);
+	TEST_ERRNO(keyctl(KEYCTL_LINK, ring, key, 0, 0), ENOTDIR);
+	TEST_ERRNO(keyctl(KEYCTL_CLEAR, key, 0, 0, 0), ENOTDIR);
+
+	// Search the nested keyring.
+	TEST_SUCC(keyctl(KEYCTL_UNLINK, key, ring, 0, 0));
+	TEST_ERRNO(keyctl(KEYCTL_UNLINK, key, ring, 0, 0), ENOENT);
+	TEST_RES(keyctl(KEYCTL_SEARCH, ring, (unsigned long)"user",
+			(unsigned long)"link_key", 0),
+		 _ret == key);
+	TEST_RES(request_key("user", "link_key", NULL, 0), _ret == key);
+	TEST_ERRNO(keyctl(KEYCTL_SEARCH, ring, (unsigned long)"user",
+			  (unsigned long)"no_such_key", 0),
+		   ENOKEY);
+	TEST_ERRNO(keyctl(KEYCTL_SEARCH, ring, (unsigned long)"no_such_type",
+			  (unsigned long)"link_key", 0),
+		   ENOKEY);
+	TEST_ERRNO(request_key("user", "no_such_key", NULL, 0), ENOKEY);
+	TEST_ERRNO(keyctl(KEYCTL_SEARCH, key, (unsigned long)"user",
+			  (unsigned long)"link_key", 0),
+		   ENOTDIR);
+
+	// Search and link the found key.
+	TEST_RES(keyctl(KEYCTL_SEARCH, ring, (unsigned long)"user",
+			(unsigned long)"link_key", ring),
+		 _ret == key);
+	TEST_RES(keyctl(KEYCTL_READ, ring, (unsigned long)serials,
+			sizeof(serials), 0),
+		 _ret == 2 * sizeof(key_serial_t));
+
+	// Move the key.
+	TEST_ERRNO(keyctl(KEYCTL_MOVE, key, ring, inner, KEYCTL_MOVE_EXCL),
+		   EEXIST);
+	TEST_ERRNO(keyctl(KEYCTL_MOVE, key, ring, inner, 2), EINVAL);
+	TEST_SUCC(keyctl(KEYCTL_UNLINK, key, inner, 0, 0));
+	TEST_SUCC(keyctl(KEYCTL_MOVE, key, ring, inner, KEYCTL_MOVE_EXCL));
+	TEST_ERRNO(keyctl(KEYCTL_MOVE, key, ring, inner, 0), ENOENT);
+	TEST_RES(keyctl(KEYCTL_READ, ring, (unsigned long)serials,
+			sizeof(serials), 0),
+		 _ret == sizeof(key_serial_t) && serials[0] == inner);
+
+	TEST_SUCC(keyctl(KEYCTL_CLEAR, inner, 0, 0, 0));
+	TEST_RES(keyctl(KEYCTL_READ, inner, 0, 0, 0), _ret == 0);
+	TEST_ERRNO(keyctl(KEYCTL_SEARCH, ring, (unsigned long)"user",
+			  (unsigned long)"link_key", 0),
+		   ENOKEY);
+
+	TEST_SUCC(keyctl(KEYCTL_UNLINK, inner, ring, 0, 0));
+}
+END_TEST()
+
+FN_TEST(revoke_and_invalidate)
+{
+	key_serial_t key;
+	char buf[64];
+
+	key = TEST_RES(add_key("user", "revoked_key", "data", 4, ring),
+		       _ret > 0);
+	TEST_SUCC(keyctl(KEYCTL_REVOKE, key, 0, 0, 0));
+	TEST_ERRNO(keyctl(KEYCTL_READ, key, (unsigned long)buf, sizeof(buf), 0),
+		   EKEYREVOKED);
+	TEST_ERRNO(keyctl(KEYCTL_DESCRIBE, key, 0, 0, 0), EKEYREVOKED);
+	TEST_SUCC(keyctl(KEYCTL_UNLINK, key, ring, 0, 0));
+
+	key = TEST_RES(add_key("user", "invalidated_key", "data", 4, ring),
+		       _ret > 0);
+	TEST_SUCC(keyctl(KEYCTL_INVALIDATE, key, 0, 0, 0));
+	TEST_ERRNO(keyctl(KEYCTL_SEARCH, ring, (unsigned long)"user",
+			  (unsigned long)"invalidated_key", 0),
+		   ENOKEY);
+}
+END_TEST()
+
+FN_TEST(timeout)
+{
+	key_serial_t key;
+
+	key = TEST_RES(add_key("user", "expired_key", "data", 4, ring),
+		       _ret > 0);
+	TEST_SUCC(keyctl(KEYCTL_SET_TIMEOUT, key, 1, 0, 0));
+	TEST_SUCC(sleep(2));
+	TEST_ERRNO(keyctl(KEYCTL_READ, key, 0, 0, 0), EKEYEXPIRED);
+	TEST_ERRNO(keyctl(KEYCTL_SET_TIMEOUT, key, 0, 0, 0), EKEYEXPIRED);
+	TEST_SUCC(keyctl(KEYCTL_UNLINK, key, ring, 0, 0));
+}
+END_TEST()
+
+FN_TEST(misc_operations)
+{
+	unsigned char caps[4] = {};
+	char buf[16];
+
+	TEST_RES(keyctl(KEYCTL_SET_REQKEY_KEYRING, KEY_REQKEY_DEFL_NO_CHANGE, 0,
+			0, 0),
+		 _ret == KEY_REQKEY_DEFL_DEFAULT);
+	TEST_RES(keyctl(KEYCTL_SET_REQKEY_KEYRING,
+			KEY_REQKEY_DEFL_SESSION_KEYRING, 0, 0, 0),
+		 _ret == KEY_REQKEY_DEFL_DEFAULT);
+	TEST_RES(keyctl(KEYCTL_SET_REQKEY_KEYRING, KEY_REQKEY_DEFL_DEFAULT, 0,
+			0, 0),
+		 _ret == KEY_REQKEY_DEFL_SESSION_KEYRING);
+	TEST_ERRNO(keyctl(KEYCTL_SET_REQKEY_KEYRING, 8, 0, 0, 0), EINVAL);
+
+	TEST_RES(keyctl(KEYCTL_GET_SECURITY, ring, (unsigned long)buf,
+			sizeof(buf), 0),
+		 _ret > 0 && buf[_ret - 1] == '\0');
+	TEST_RES(keyctl(KEYCTL_CAPABILITIES, (unsigned long)caps, sizeof(caps),
+			0, 0),
+		 _ret > 0 && (caps[0] & KEYCTL_CAPS0_CAPABILITIES) &&
+			 (caps[0] & KEYCTL_CAPS0_INVALIDATE) &&
+			 (caps[0] & KEYCTL_CAPS0_MOVE));
+
+	TEST_SUCC(keyctl(KEYCTL_ASSUME_AUTHORITY, 0, 0, 0, 0));
+	TEST_ERRNO(keyctl(KEYCTL_INSTANTIATE, ring, 0, 0, 0), EPERM);
+	TEST_ERRNO(keyctl(1000, 0, 0, 0, 0), EOPNOTSUPP);
+}
+END_TEST()
+
+FN_TEST(session_inheritance)
+{
+	key_serial_t key, new_session;
+	int status;
+	pid_t pid;
+
+	key = TEST_RES(add_key("user", "inherited_key", "data", 4,
+			       KEY_SPEC_SESSION_KEYRING),
+		       _ret > 0);
+
+	// The session keyring is inherited by the child, and the child can
+	// install its new session keyring in the parent.
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		CHECK_WITH(request_key("user", "inherited_key", NULL, 0),
+			   _ret == key);
+		CHECK_WITH(keyctl(KEYCTL_GET_KEYRING_ID,
+				  KEY_SPEC_PROCESS_KEYRING, 0, 0, 0),
+			   _ret < 0 && errno == ENOKEY);
+
+		new_session = CHECK(keyctl(KEYCTL_JOIN_SESSION_KEYRING, 0, 0, 0,
+					   0));
+		pid = CHECK(fork());
+		if (pid == 0) {
+			CHECK(keyctl(KEYCTL_JOIN_SESSION_KEYRING,
+				     (unsigned long)"named_session", 0, 0, 0));
+			CHECK(keyctl(KEYCTL_SESSION_TO_PARENT, 0, 0, 0, 0));
+			exit(EXIT_SUCCESS);
+		}
+		CHECK_WITH(wait(&status), _ret == pid && status == 0);
+
+		CHECK_WITH(keyctl(KEYCTL_GET_KEYRING_ID,
+				  KEY_SPEC_SESSION_KEYRING, 0, 0, 0),
+			   _ret > 0 && _ret != new_session);
+		CHECK_WITH(request_key("user", "inherited_key", NULL, 0),
+			   _ret < 0 && errno == ENOKEY);
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(wait(&status), _ret == pid && status == 0);
+
+	TEST_SUCC(keyctl(KEYCTL_UNLINK, key, session, 0, 0));
+}
+END_TEST()
@@ -0,0 +1,506 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <fcntl.h>
+#include <netinet/in.h>
+#include <stdint.h>
+#include <sys/prctl.h>
+#include <sys/socket.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "../../common/test.h"
+
+// The definitions are copied from `<linux/landlock.h>`, which may be too old
+// to contain the access rights to TCP ports.
+
+struct landlock_ruleset_attr {
+	uint64_t handled_access_fs;
+	uint64_t handled_access_net;
+};
+
+struct landlock_path_beneath_attr {
+	uint64_t allowed_access;
+	int32_t parent_fd;
+} __attribute__((packed));
+
+struct landlock_net_port_attr {
+	uint64_t allowed_access;
+	uint64_t port;
+};
+
+#define LANDLOCK_CREATE_RULESET_VERSION (1U << 0)
+
+#define LANDLOCK_RULE_PATH_BENEATH 1
+#define LANDLOCK_RULE_NET_PORT 2
+
+#define LANDLOCK_ACCESS_FS_WRITE_FILE (1ULL << 1)
+#define LANDLOCK_ACCESS_FS_READ_FILE (1ULL << 2)
+#define LANDLOCK_ACCESS_FS_READ_DIR (1ULL << 3)
+#define LANDLOCK_ACCESS_FS_REMOVE_FILE (1ULL << 5)
+#define LANDLOCK_ACCESS_FS_MAKE_DIR (1ULL << 7)
+#define LANDLOCK_ACCESS_FS_MAKE_REG (1ULL << 8)
+#define LANDLOCK_ACCESS_FS_REFER (1ULL << 13)
+#define LANDLOCK_ACCESS_FS_TRUNCATE (1ULL << 14)
+
+#define LANDLOCK_ACCESS_NET_BIND_TCP (1ULL << 0)
+#define LANDLOCK_ACCESS_NET_CONNECT_TCP (1ULL << 1)
+
+static int landlock_create_ruleset(const struct landlock_ruleset_attr *attr,
+				   size_t size, uint32_t flags)
+{
+	return syscall(SYS_landlock_create_ruleset, attr, size, flags);
+}
+
+static int landlock_add_rule(int ruleset_fd, int rule_type,
+			     const void *rule_attr, uint32_t flags)
+{
+	return syscall(SYS_landlock_add_rule, ruleset_fd, rule_type, rule_attr,
+		       flags);
+}
+
+static int landlock_restrict_self(int ruleset_fd, uint32_t flags)
+{
+	return syscall(SYS_landlock_restrict_self, ruleset_fd, flags);
+}
+
+#define DIR_A "/tmp/landlock_a"
+#define DIR_B "/tmp/landlock_b"
+#define DIR_DENIED "/tmp/landlock_denied"
+
+#define FS_ACCESS                                                   \
+	(LANDLOCK_ACCESS_FS_WRITE_FILE | LANDLOCK_ACCESS_FS_READ_FILE | \
+	 LANDLOCK_ACCESS_FS_READ_DIR | LANDLOCK_ACCESS_FS_REMOVE_FILE | \
+	 LANDLOCK_ACCESS_FS_MAKE_DIR | LANDLOCK_ACCESS_FS_MAKE_REG |     \
+	 LANDLOCK_ACCESS_FS_TRUNCATE)
+
+#define PAGE_SIZE 4096
+
+#define PORT_ALLOWED 8081
+#define PORT_DENIED 8082
+
+static int create_file(const char *path)
+{
+	int fd;
+
+	fd = open(path, O_WRONLY | O_CREAT | O_TRUNC, 0644);
+	if (fd < 0)
+		return -1;
+	return close(fd);
+}
+
+FN_SETUP(files)
+{
+	CHECK(mkdir(DIR_A, 0755));
+	CHECK(mkdir(DIR_B, 0755));
+	CHECK(mkdir(DIR_DENIED, 0755));
+	CHECK(create_file(DIR_A "/file"));
+	CHECK(create_file(DIR_DENIED "/file"));
+}
+END_SETUP()
+
+FN_TEST(abi_version)
+{
+	TEST_RES(landlock_create_ruleset(NULL, 0,
+					 LANDLOCK_CREATE_RULESET_VERSION),
+		 _ret >= 4);
+	TEST_ERRNO(landlock_create_ruleset(NULL, 1,
+					   LANDLOCK_CREATE_RULESET_VERSION),
+		   EINVAL);
+}
+END_TEST()
+
+FN_TEST(create_ruleset_invalid)
+{
+	struct landlock_ruleset_attr attr = {};
+	char big_attr[PAGE_SIZE] = {};
+
+	TEST_ERRNO(landlock_create_ruleset(&attr, sizeof(attr), 1U << 31),
+		   EINVAL);
+	TEST_ERRNO(landlock_create_ruleset(NULL, sizeof(attr), 0), EFAULT);
+
+	attr.handled_access_fs = LANDLOCK_ACCESS_FS_READ_FILE;
+	TEST_ERRNO(landlock_create_ruleset(&attr, 4, 0), EINVAL);
+
+	attr.handled_access_fs = 1ULL << 63;
+	TEST_ERRNO(landlock_create_ruleset(&attr, sizeof(attr), 0), EINVAL);
+
+	attr.handled_access_fs = 0;
+	attr.handled_access_net = 1ULL << 63;
+	TEST_ERRNO(landlock_create_ruleset(&attr, sizeof(attr), 0), EINVAL);
+
+	attr.handled_access_net = 0;
+	TEST_ERRNO(landlock_create_ruleset(&attr, sizeof(attr), 0), ENOMSG);
+
+	big_attr[0] = LANDLOCK_ACCESS_FS_READ_FILE;
+	big_attr[PAGE_SIZE - 1] = 1;
+	TEST_ERRNO(landlock_create_ruleset((void *)big_attr, sizeof(big_attr),
+					   0),
+		   E2BIG);
+}
+END_TEST()
+
+FN_TEST(add_rule_invalid)
+{
+	struct landlock_ruleset_attr attr = {
+		.handled_access_fs = LANDLOCK_ACCESS_FS_READ_FILE |
+				     LANDLOCK_ACCESS_FS_READ_DIR,
+		.handled_access_net = LANDLOCK_ACCESS_NET_BIND_TCP,
+	};
+	struct landlock_path_beneath_attr path_beneath;
+	struct landlock_net_port_attr net_port;
+	int ruleset_fd, dir_fd, file_fd, pipe_fds[2];
+
+	ruleset_fd =
+		TEST_SUCC(landlock_create_ruleset(&attr, sizeof(attr), 0));
+	dir_fd = TEST_SUCC(open(DIR_A, O_PATH | O_DIRECTORY));
+	file_fd = TEST_SUCC(open(DIR_A "/file", O_RDONLY));
+	TEST_SUCC(pipe(pipe_fds));
+
+	path_beneath.allowed_access = LANDLOCK_ACCESS_FS_READ_FILE;
+	path_beneath.parent_fd = dir_fd;
+	TEST_ERRNO(landlock_add_rule(ruleset_fd, LANDLOCK_RULE_PATH_BENEATH,
+				     &path_beneath, 1),
+		   EINVAL);
+	TEST_ERRNO(landlock_add_rule(file_fd, LANDLOCK_RULE_PATH_BENEATH,
+				     &path_beneath, 0),
+		   EBADFD);
+	TEST_ERRNO(landlock_add_rule(ruleset_fd, 3, &path_beneath, 0), EINVAL);
+	TEST_ERRNO(landlock_add_rule(ruleset_fd, LANDLOCK_RULE_PATH_BENEATH,
+				     NULL, 0),
+		   EFAULT);
+
+	path_beneath.allowed_access = 0;
+	TEST_ERRNO(landlock_add_rule(ruleset_fd, LANDLOCK_RULE_PATH_BENEATH,
+				     &path_beneath, 0),
+		   ENOMSG);
+
+	path_beneath.allowed_access = LANDLOCK_ACCESS_FS_WRITE_FILE;
+	TEST_ERRNO(landlock_add_rule(ruleset_fd, LANDLOCK_RULE_PATH_BENEATH,
+				     &path_beneath, 0),
+		   EINVAL);
+
+	path_beneath.allowed_access = LANDLOCK_ACCESS_FS_READ_DIR;
+	path_beneath.parent_fd = file_fd;
+	TEST_ERRNO(landlock_add_rule(ruleset_fd, LANDLOCK_RULE_PATH_BENEATH,
+				     &path_beneath, 0),
+		   EINVAL);
+
+	path_beneath.parent_fd = pipe_fds[0];
+	TEST_ERRNO(landlock_add_rule(ruleset_fd, LANDLOCK_RULE_PATH_BENEATH,
+				     &path_beneath, 0),
+		   EBADFD);
+
+	path_beneath.allowed_access = LANDLOCK_ACCESS_FS_READ_FILE;
+	path_beneath.parent_fd = file_fd;
+	TEST_SUCC(landlock_add_rule(ruleset_fd, LANDLOCK_RULE_PATH_BENEATH,
+				    &path_beneath, 0));
+	path_beneath.allowed_access |= LANDLOCK_ACCESS_FS_READ_DIR;
+	path_beneath.parent_fd = dir_fd;
+	TEST_SUCC(landlock_add_rule(ruleset_fd, LANDLOCK_RULE_PATH_BENEATH,
+				    &path_beneath, 0));
+
+	net_port.allowed_access = LANDLOCK_ACCESS_NET_CONNECT_TCP;
+	net_port.port = PORT_ALLOWED;
+	TEST_ERRNO(landlock_add_rule(ruleset_fd, LANDLOCK_RULE_NET_PORT,
+				     &net_port, 0),
+		   EINVAL);
+
+	net_port.allowed_access = LANDLOCK_ACCESS_NET_BIND_TCP;
+	net_port.port = 65536;
+	TEST_ERRNO(landlock_add_rule(ruleset_fd, LANDLOCK_RULE_NET_PORT,
+				     &net_port, 0),
+		   EINVAL);
+
+	net_port.port = PORT_ALLOWED;
+	TEST_SUCC(landlock_add_rule(ruleset_fd, LANDLOCK_RULE_NET_PORT,
+				    &net_port, 0));
+
+	TEST_SUCC(close(pipe_fds[0]));
+	TEST_SUCC(close(pipe_fds[1]));
+	TEST_SUCC(close(file_fd));
+	TEST_SUCC(close(dir_fd));
+	TEST_SUCC(close(ruleset_fd));
+}
+END_TEST()
+
+FN_TEST(no_new_privs)
+{
+	TEST_RES(prctl(PR_GET_NO_NEW_PRIVS, 0, 0, 0, 0), _ret == 0);
+	TEST_ERRNO(prctl(PR_SET_NO_NEW_PRIVS, 2, 0, 0, 0), EINVAL);
+	TEST_ERRNO(prctl(PR_SET_NO_NEW_PRIVS, 0, 0, 0, 0), EINVAL);
+}
+END_TEST()
+
+static int create_fs_ruleset(uint64_t handled_access)
+{
+	struct landlock_ruleset_attr attr = {
+		.handled_access_fs = handled_access,
+	};
+	struct landlock_path_beneath_attr path_beneath = {
+		.allowed_access = handled_access,
+	};
+	int ruleset_fd;
+
+	ruleset_fd = CHECK(landlock_create_ruleset(&attr, sizeof(attr), 0));
+
+	path_beneath.parent_fd = CHECK(open(DIR_A, O_PATH));
+	CHECK(landlock_add_rule(ruleset_fd, LANDLOCK_RULE_PATH_BENEATH,
+				&path_beneath, 0));
+	CHECK(close(path_beneath.parent_fd));
+
+	path_beneath.parent_fd = CHECK(open(DIR_B, O_PATH));
+	CHECK(landlock_add_rule(ruleset_fd, LANDLOCK_RULE_PATH_BENEATH,
+				&path_beneath, 0));
+	CHECK(close(path_beneath.parent_fd));
+
+	return ruleset_fd;
+}
+
+static void restrict_fs(uint64_t handled_access)
+{
+	int ruleset_fd;
+
+	ruleset_fd = create_fs_ruleset(handled_access);
+	CHECK(prctl(PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0));
+	CHECK(landlock_restrict_self(ruleset_fd, 0));
+	CHECK(close(ruleset_fd));
+}
+
+#define TEST_IN_CHILD(func)                                               \
+	({                                                                \
+		pid_t pid;                                                \
+		int status;                                               \
+                                                                          \
+		pid = TEST_SUCC(fork());                                  \
+		if (pid == 0) {                                           \
+			func();                                           \
+			exit(EXIT_SUCCESS);                               \
+		}                                                         \
+		TEST_RES(waitpid(pid, &status, 0),                        \
+			 _ret == pid && WIFEXITED(status) &&              \
+				 WEXITSTATUS(status) == EXIT_SUCCESS);    \
+	})
+
+static void restrict_without_no_new_privs(void)
+{
+	int ruleset_fd;
+
+	ruleset_fd = create_fs_ruleset(LANDLOCK_ACCESS_FS_READ_FILE);
+
+	// Drop `CAP_SYS_ADMIN`.
+	CHECK(setresuid(65534, 65534, 65534));
+	CHECK_WITH(landlock_restrict_self(ruleset_fd, 0),
+		   _ret == -1 && errno == EPERM);
+
+	CHECK(prctl(PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0));
+	CHECK_WITH(prctl(PR_GET_NO_NEW_PRIVS, 0, 0, 0, 0), _ret == 1);
+	CHECK_WITH(landlock_restrict_self(ruleset_fd, 1U << 31),
+		   _ret == -1 && errno == EINVAL);
+	CHECK_WITH(landlock_restrict_self(STDIN_FILENO, 0),
+		   _ret == -1 && errno == EBADFD);
+	CHECK(landlock_restrict_self(ruleset_fd, 0));
+}
+
+FN_TEST(restrict_self)
+{
+	TEST_IN_CHILD(restrict_without_no_new_privs);
+}
+END_TEST()
+
+static void open_files(void)
+{
+	restrict_fs(FS_ACCESS);
+
+	CHECK(close(CHECK(open(DIR_A "/file", O_RDWR))));
+	CHECK(close(CHECK(open(DIR_A, O_RDONLY | O_DIRECTORY))));
+	CHECK_WITH(open(DIR_DENIED "/file", O_RDONLY),
+		   _ret == -1 && errno == EACCES);
+	CHECK_WITH(open(DIR_DENIED "/file", O_WRONLY),
+		   _ret == -1 && errno == EACCES);
+	CHECK_WITH(open(DIR_DENIED, O_RDONLY | O_DIRECTORY),
+		   _ret == -1 && errno == EACCES);
+
+	// Opening a file with `O_PATH` does not require any access rights.
+	CHECK(close(CHECK(open(DIR_DENIED "/file", O_PATH))));
+	CHECK(access(DIR_DENIED "/file", F_OK));
+}
+
+static void create_and_remove_files(void)
+{
+	restrict_fs(FS_ACCESS);
+
+	CHECK(mkdir(DIR_A "/dir", 0755));
+	CHECK(rmdir(DIR_A "/dir"));
+	CHECK_WITH(mkdir(DIR_DENIED "/dir", 0755),
+		   _ret == -1 && errno == EACCES);
+
+	CHECK(create_file(DIR_A "/new_file"));
+	CHECK(unlink(DIR_A "/new_file"));
+	CHECK_WITH(create_file(DIR_DENIED "/new_file"),
+		   _ret == -1 && errno == EACCES);
+	CHECK_WITH(unlink(DIR_DENIED "/file"), _ret == -1 && errno == EACCES);
+
+	CHECK(truncate(DIR_A "/file", 0));
+	CHECK_WITH(truncate(DIR_DENIED "/file", 0),
+		   _ret == -1 && errno == EACCES);
+}
+
+static void rename_files(void)
+{
+	restrict_fs(FS_ACCESS);
+
+	CHECK(create_file(DIR_A "/old_file"));
+	CHECK(rename(DIR_A "/old_file", DIR_A "/new_file"));
+
+	// `LANDLOCK_ACCESS_FS_REFER` is always denied unless it is granted.
+	CHECK_WITH(rename(DIR_A "/new_file", DIR_B "/new_file"),
+		   _ret == -1 && errno == EXDEV);
+	CHECK_WITH(link(DIR_A "/new_file", DIR_B "/new_file"),
+		   _ret == -1 && errno == EXDEV);
+	CHECK_WITH(rename(DIR_A "/new_file", DIR_DENIED "/new_file"),
+		   _ret == -1 && errno == EACCES);
+
+	CHECK(unlink(DIR_A "/new_file"));
+}
+
+static void rename_files_with_refer(void)
+{
+	restrict_fs(FS_ACCESS | LANDLOCK_ACCESS_FS_REFER);
+
+	CHECK(create_file(DIR_A "/old_file"));
+	CHECK(rename(DIR_A "/old_file", DIR_B "/new_file"));
+	CHECK(link(DIR_B "/new_file", DIR_A "/old_file"));
+	CHECK(unlink(DIR_A "/old_file"));
+	CHECK(unlink(DIR_B "/new_file"));
+}
+
+static void inherit_domain(void)
+{
+	pid_t pid;
+	int status;
+
+	restrict_fs(FS_ACCESS);
+
+	pid = CHECK(fork());
+	if (pid == 0) {
+		CHECK_WITH(open(DIR_DENIED "/file", O_RDONLY),
+			   _ret == -1 && errno == EACCES);
+		exit(EXIT_SUCCESS);
+	}
+	CHECK_WITH(waitpid(pid, &status, 0),
+		   _ret == pid && WIFEXITED(status) &&
+			   WEXITSTATUS(status) == EXIT_SUCCESS);
+}
+
+static void stack_domains(void)
+{
+	int i, ruleset_fd;
+
+	// Each layer grants `LANDLOCK_ACCESS_FS_READ_FILE` to the first
+	// directory, so the access is still allowed.
+	for (i = 0; i < 16; i++)
+		restrict_fs(LANDLOCK_ACCESS_FS_READ_FILE);
+	CHECK(close(CHECK(open(DIR_A "/file", O_RDONLY))));
+
+	ruleset_fd = create_fs_ruleset(LANDLOCK_ACCESS_FS_READ_FILE);
+	CHECK_WITH(landlock_restrict_self(ruleset_fd, 0),
+		   _ret == -1 && errno == E2BIG);
+}
+
+FN_TEST(fs_access)
+{
+	TEST_IN_CHILD(open_files);
+	TEST_IN_CHILD(create_and_remove_files);
+	TEST_IN_CHILD(rename_files);
+	TEST_IN_CHILD(rename_files_with_refer);
+	TEST_IN_CHILD(inherit_domain);
+	TEST_IN_CHILD(stack_domains);
+}
+END_TEST()
+
+static int bind_port(int type, int port)
+{
+	struct sockaddr_in addr = {
+		.sin_family = AF_INET,
+		.sin_port = htons(port),
+		.sin_addr = { .s_addr = htonl(INADDR_LOOPBACK) },
+	};
+	int sk, ret, err;
+
+	sk = CHECK(socket(AF_INET, type, 0));
+	ret = bind(sk, (struct sockaddr *)&addr, sizeof(addr));
+	err = errno;
+	CHECK(close(sk));
+
+	errno = err;
+	return ret;
+}
+
+static int connect_port(int port)
+{
+	struct sockaddr_in addr = {
+		.sin_family = AF_INET,
+		.sin_port = htons(port),
+		.sin_addr = { .s_addr = htonl(INADDR_LOOPBACK) },
+	};
+	int sk, ret, err;
+
+	sk = CHECK(socket(AF_INET, SOCK_STREAM, 0));
+	ret = connect(sk, (struct sockaddr *)&addr, sizeof(addr));
+	err = errno;
+	CHECK(close(sk));
+
+	errno = err;
+	return ret;
+}
+
+static void bind_and_connect(void)
+{
+	struct landlock_ruleset_attr attr = {
+		.handled_access_net = LANDLOCK_ACCESS_NET_BIND_TCP |
+				      LANDLOCK_ACCESS_NET_CONNECT_TCP,
+	};
+	struct landlock_net_port_attr net_port = {
+		.allowed_access = LANDLOCK_ACCESS_NET_BIND_TCP,
+		.port = PORT_ALLOWED,
+	};
+	int ruleset_fd;
+
+	ruleset_fd = CHECK(landlock_create_ruleset(&attr, sizeof(attr), 0));
+	CHECK(landlock_add_rule(ruleset_fd, LANDLOCK_RULE_NET_PORT, &net_port,
+				0));
+	CHECK(prctl(PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0));
+	CHECK(landlock_restrict_self(ruleset_fd, 0));
+	CHECK(close(ruleset_fd));
+
+	CHECK(bind_port(SOCK_STREAM, PORT_ALLOWED));
+	CHECK_WITH(bind_port(SOCK_STREAM, PORT_DENIED),
+		   _ret == -1 && errno == EACCES);
+	CHECK_WITH(connect_port(PORT_ALLOWED), _ret == -1 && errno == EACCES);
+
+	// Only TCP sockets are restricted.
+	CHECK(bind_port(SOCK_DGRAM, PORT_DENIED));
+
+	// File accesses are not restricted.
+	CHECK(close(CHECK(open(DIR_DENIED "/file", O_RDONLY))));
+}
+
+FN_TEST(net_access)
+{
+	TEST_IN_CHILD(bind_and_connect);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(unlink(DIR_A "/file"));
+	CHECK(unlink(DIR_DENIED "/file"));
+	CHECK(rmdir(DIR_A));
+	CHECK(rmdir(DIR_B));
+	CHECK(rmdir(DIR_DENIED));
+}
+END_SETUP()
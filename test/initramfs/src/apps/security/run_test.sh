@@ -4,10 +4,18 @@
 
 set -e
 
+./apparmor/apparmor
+
+./audit/audit
+
 ./capability/capabilities
 ./capability/capset
 ./capability/execve
 
+./keys/keys
+
+./landlock/landlock
+
 ./namespace/mnt_ns
 ./namespace/proc_nsfs
 ./namespace/setns
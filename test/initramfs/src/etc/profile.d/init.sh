@@ -8,5 +8,8 @@ mount -t sysfs none /sys
 mount -t proc none /proc
 mount -t cgroup2 none /sys/fs/cgroup
 mount -t configfs none /sys/kernel/config
+mount -t debugfs none /sys/kernel/debug
+mount -t tracefs none /sys/kernel/tracing
+mount -t securityfs none /sys/kernel/security
 mount -t ext2 /dev/vda /ext2
 mount -t exfat /dev/vdb /exfat
\ No newline at end of file
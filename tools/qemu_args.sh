@@ -55,6 +55,9 @@ else
     CONSOLE_ARGS="-serial chardev:mux"
 fi
 
+# A virtio console port for testing. Its host side is never connected.
+VIRTIO_PORT_ARGS="-chardev null,id=vport -device virtserialport,chardev=vport,name=org.asterinas.test.0"
+
 if [ "$1" = "tdx" ]; then
     TDX_OBJECT='{ "qom-type": "tdx-guest", "id": "tdx0", "sept-ve-disable": true, "quote-generation-socket": { "type": "vsock", "cid": "2", "port": "4050" } }'
 
@@ -120,6 +123,14 @@ QEMU_ARGS="\
     -device virtio-blk-pci,bus=pcie.0,addr=0x7,drive=x1,serial=vexfat,disable-legacy=on,disable-modern=off,queue-size=64,num-queues=1,request-merging=off,backend_defaults=off,discard=off,write-zeroes=off,event_idx=off,indirect_desc=off,queue_reset=off$IOMMU_DEV_EXTRA \
     -device virtio-net-pci,netdev=net01,disable-legacy=on,disable-modern=off$VIRTIO_NET_FEATURES$IOMMU_DEV_EXTRA \
     -device virtio-serial-pci,disable-legacy=on,disable-modern=off$IOMMU_DEV_EXTRA \
+    $VIRTIO_PORT_ARGS \
+    -device virtio-rng-pci,disable-legacy=on,disable-modern=off$IOMMU_DEV_EXTRA \
+    -device virtio-gpu-pci,disable-legacy=on,disable-modern=off$IOMMU_DEV_EXTRA \
+    -device virtio-tablet-pci,disable-legacy=on,disable-modern=off$IOMMU_DEV_EXTRA \
+    -audiodev none,id=snd0 \
+    -device virtio-sound-pci,audiodev=snd0,disable-legacy=on,disable-modern=off$IOMMU_DEV_EXTRA \
+    -device qemu-xhci,id=xhci \
+    -device usb-hub,bus=xhci.0,port=1 \
     $CONSOLE_ARGS \
     $IOMMU_EXTRA_ARGS \
 "
@@ -132,8 +143,14 @@ MICROVM_QEMU_ARGS="\
     -device virtio-blk-device,drive=x0,serial=vext2 \
     -device virtio-blk-device,drive=x1,serial=vexfat \
     -device virtio-keyboard-device \
+    -device virtio-tablet-device \
     -device virtio-net-device,netdev=net01 \
     -device virtio-serial-device \
+    $VIRTIO_PORT_ARGS \
+    -device virtio-rng-device \
+    -device virtio-gpu-device \
+    -audiodev none,id=snd0 \
+    -device virtio-sound-device,audiodev=snd0 \
     $CONSOLE_ARGS \
 "
 